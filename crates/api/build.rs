@@ -0,0 +1,5 @@
+//! Embeds build metadata read back through `erp_build_info::build_info!`.
+
+fn main() {
+    erp_build_info::emit();
+}
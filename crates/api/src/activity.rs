@@ -0,0 +1,1093 @@
+//! # Tenant Activity Feed
+//!
+//! One "what happened" view per tenant under `/api/v1/activity`. A projector
+//! polls three sources every `activity.poll_interval_ms` and normalizes each
+//! event into a row of `public.tenant_activity` (actor, verb, object,
+//! summary, severity, time):
+//!
+//! - `audit`: `audit_events`, by `created_at`
+//! - `customer`: `customer_events`, by `recorded_at`
+//! - `inventory`: `inventory_transactions`, by `created_at`; the tenant is
+//!   the owner of the product
+//!
+//! Each source has a high-water mark in `public.activity_projection_marks`,
+//! advanced in the same transaction as the rows it covers. Projection is
+//! at-least-once: rows are unique on `(source, source_id)`, so replaying a
+//! source after a crash or a reset mark inserts nothing twice. Events younger
+//! than [`SETTLE_SECS`] are left for the next poll so rows committed late are
+//! not skipped.
+//!
+//! Summaries pass through [`mask_summary`]: tax numbers found in the event
+//! payload, and anything shaped like a VAT id, never reach the feed.
+//!
+//! The feed is ordered newest first with keyset pagination on
+//! `(occurred_at, id)`. Old rows are removed by the nightly retention job
+//! (category `tenant_activity`, default `activity.retention_days`).
+//!
+//! [`ActivityService::export`] collects up to [`EXPORT_MAX_ROWS`] matching
+//! entries for the audit export, which [`export_csv`] writes below the
+//! tenant's export header.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use erp_core::retention::{RetentionAction, RetentionCategory, RetentionHandler};
+use erp_core::export_header::{csv_field, ExportHeader};
+use erp_core::{ActivityConfig, Error, ErrorCode, Result};
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::{PgPool, Row};
+use std::sync::Arc;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// Events younger than this are not projected yet
+pub const SETTLE_SECS: i64 = 5;
+
+/// Replacement for masked values in summaries
+pub const MASK: &str = "***";
+
+/// Longest summary stored, in characters
+const MAX_SUMMARY_CHARS: usize = 500;
+
+/// Most entries in one audit export
+pub const EXPORT_MAX_ROWS: usize = 10_000;
+
+/// Where an activity entry was projected from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActivitySource {
+    Audit,
+    Customer,
+    Inventory,
+}
+
+impl ActivitySource {
+    pub const ALL: [ActivitySource; 3] = [ActivitySource::Audit, ActivitySource::Customer, ActivitySource::Inventory];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ActivitySource::Audit => "audit",
+            ActivitySource::Customer => "customer",
+            ActivitySource::Inventory => "inventory",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "audit" => Some(ActivitySource::Audit),
+            "customer" => Some(ActivitySource::Customer),
+            "inventory" => Some(ActivitySource::Inventory),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActivitySeverity {
+    Info,
+    Warning,
+    Critical,
+}
+
+impl ActivitySeverity {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ActivitySeverity::Info => "info",
+            ActivitySeverity::Warning => "warning",
+            ActivitySeverity::Critical => "critical",
+        }
+    }
+
+    /// Case-insensitive, so audit severities (`Info`, `Warning`, ...) parse as stored
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "info" => Some(ActivitySeverity::Info),
+            "warning" => Some(ActivitySeverity::Warning),
+            "critical" => Some(ActivitySeverity::Critical),
+            _ => None,
+        }
+    }
+}
+
+/// Position in a source: ordered by timestamp, ties broken by source id
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HighWaterMark {
+    pub position: DateTime<Utc>,
+    pub source_id: String,
+}
+
+/// An event as read from one of the sources, before normalization
+#[derive(Debug, Clone)]
+pub struct SourceEvent {
+    pub source: ActivitySource,
+    pub source_id: String,
+    /// Ordering column of the source
+    pub position: DateTime<Utc>,
+    /// None for platform events outside any tenant
+    pub tenant_id: Option<Uuid>,
+    pub actor_id: Option<Uuid>,
+    /// Audit event type, customer event type or inventory transaction type
+    pub kind: String,
+    pub object_type: Option<String>,
+    pub object_id: Option<String>,
+    pub occurred_at: DateTime<Utc>,
+    /// Only audit events carry a severity
+    pub severity: Option<String>,
+    /// Only audit events carry a description
+    pub description: Option<String>,
+    pub data: Value,
+}
+
+/// One normalized row of the feed
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ActivityEntry {
+    /// Assigned when stored
+    pub id: i64,
+    pub tenant_id: Uuid,
+    pub source: ActivitySource,
+    pub source_id: String,
+    pub actor_id: Option<Uuid>,
+    pub verb: String,
+    pub object_type: String,
+    pub object_id: Option<String>,
+    pub summary: String,
+    pub severity: ActivitySeverity,
+    pub occurred_at: DateTime<Utc>,
+}
+
+/// Position in the feed: entries strictly older than this come next
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ActivityCursor {
+    pub occurred_at: DateTime<Utc>,
+    pub id: i64,
+}
+
+impl ActivityCursor {
+    pub fn after(entry: &ActivityEntry) -> Self {
+        Self {
+            occurred_at: entry.occurred_at,
+            id: entry.id,
+        }
+    }
+
+    /// `{microseconds since epoch}_{id}`; the database keeps microseconds, so it round-trips
+    pub fn encode(&self) -> String {
+        format!("{}_{}", self.occurred_at.timestamp_micros(), self.id)
+    }
+
+    pub fn decode(value: &str) -> Option<Self> {
+        let (micros, id) = value.split_once('_')?;
+        Some(Self {
+            occurred_at: DateTime::from_timestamp_micros(micros.parse().ok()?)?,
+            id: id.parse().ok()?,
+        })
+    }
+}
+
+/// Filters of a feed request
+#[derive(Debug, Clone, Default)]
+pub struct ActivityQuery {
+    /// Object types; empty for all
+    pub object_types: Vec<String>,
+    pub actor_id: Option<Uuid>,
+    pub since: Option<DateTime<Utc>>,
+    /// Case-insensitive substring of the summary
+    pub text: Option<String>,
+    pub cursor: Option<ActivityCursor>,
+}
+
+/// One page of the feed
+#[derive(Debug, Clone, Serialize)]
+pub struct ActivityPage {
+    pub entries: Vec<ActivityEntry>,
+    pub next_cursor: Option<String>,
+    pub has_more: bool,
+}
+
+/// `AuthenticationSuccess` -> `authentication_success`, `CUSTOM_EXPORT` -> `custom_export`
+fn snake_case(value: &str) -> String {
+    if value.contains('_') || !value.chars().any(|c| c.is_ascii_lowercase()) {
+        return value.to_ascii_lowercase();
+    }
+
+    let mut out = String::with_capacity(value.len() + 4);
+    for (i, c) in value.chars().enumerate() {
+        if c.is_ascii_uppercase() && i > 0 {
+            out.push('_');
+        }
+        out.push(c.to_ascii_lowercase());
+    }
+    out
+}
+
+/// `customer_soft_deleted` -> `Customer soft deleted`
+fn sentence(verb: &str) -> String {
+    let words = verb.replace('_', " ");
+    let mut chars = words.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => words,
+    }
+}
+
+/// First string field of `data` among `keys`
+fn text_field<'a>(data: &'a Value, keys: &[&str]) -> Option<&'a str> {
+    keys.iter()
+        .find_map(|key| data.get(*key).and_then(Value::as_str))
+        .filter(|value| !value.is_empty())
+}
+
+fn customer_severity(verb: &str) -> ActivitySeverity {
+    match verb {
+        "customer_soft_deleted" | "credit_status_changed" | "risk_rating_updated" | "compliance_status_changed" => {
+            ActivitySeverity::Warning
+        }
+        _ => ActivitySeverity::Info,
+    }
+}
+
+/// Normalize a source event; None for events outside any tenant
+pub fn project(event: &SourceEvent) -> Option<ActivityEntry> {
+    let tenant_id = event.tenant_id?;
+
+    let (verb, object_type, summary, severity) = match event.source {
+        ActivitySource::Audit => {
+            let verb = snake_case(&event.kind);
+            let summary = event.description.clone().unwrap_or_else(|| sentence(&verb));
+            let severity = event
+                .severity
+                .as_deref()
+                .and_then(ActivitySeverity::parse)
+                .unwrap_or(ActivitySeverity::Info);
+            let object_type = event.object_type.clone().unwrap_or_else(|| "system".to_string());
+            (verb, object_type, summary, severity)
+        }
+        ActivitySource::Customer => {
+            let verb = snake_case(&event.kind);
+            // Stored as {"event_type": ..., "data": {...}}
+            let fields = event.data.get("data").unwrap_or(&event.data);
+            let mut summary = sentence(&verb);
+            if let Some(name) = text_field(fields, &["legal_name", "new_legal_name", "customer_number"]) {
+                summary = format!("{}: {}", summary, name);
+            }
+            if let Some(reason) = text_field(fields, &["reason"]) {
+                summary = format!("{} ({})", summary, reason);
+            }
+            let severity = customer_severity(&verb);
+            (verb, "customer".to_string(), summary, severity)
+        }
+        ActivitySource::Inventory => {
+            let verb = snake_case(&event.kind);
+            let quantity = event.data.get("quantity_change").and_then(Value::as_f64).unwrap_or(0.0);
+            let product = text_field(&event.data, &["sku"])
+                .map(str::to_string)
+                .or_else(|| event.object_id.clone())
+                .unwrap_or_else(|| "unknown product".to_string());
+            let mut summary = format!("{} of {:+} x {}", sentence(&verb), quantity, product);
+            if let Some(reference) = text_field(&event.data, &["reference_number", "reason_code"]) {
+                summary = format!("{} ({})", summary, reference);
+            }
+            let severity = if verb == "adjustment" && quantity < 0.0 {
+                ActivitySeverity::Warning
+            } else {
+                ActivitySeverity::Info
+            };
+            (verb, "product".to_string(), summary, severity)
+        }
+    };
+
+    Some(ActivityEntry {
+        id: 0,
+        tenant_id,
+        source: event.source,
+        source_id: event.source_id.clone(),
+        actor_id: event.actor_id,
+        verb,
+        object_type,
+        object_id: event.object_id.clone(),
+        summary: mask_summary(&summary, &event.data),
+        severity,
+        occurred_at: event.occurred_at,
+    })
+}
+
+fn is_tax_key(key: &str) -> bool {
+    let key = key.to_ascii_lowercase();
+    key.contains("tax_number") || key.contains("tax_id") || key.starts_with("vat") || key.contains("_vat")
+}
+
+/// String values held under tax number fields anywhere in `data`
+fn tax_values(data: &Value, under_tax_key: bool, out: &mut Vec<String>) {
+    match data {
+        Value::String(s) if under_tax_key && s.len() >= 4 => out.push(s.clone()),
+        Value::Array(items) => items.iter().for_each(|item| tax_values(item, under_tax_key, out)),
+        Value::Object(map) => {
+            for (key, value) in map {
+                tax_values(value, under_tax_key || is_tax_key(key), out);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Two country letters and 8 to 12 further characters, mostly digits (`DE123456789`)
+fn looks_like_vat_id(token: &str) -> bool {
+    let bytes = token.as_bytes();
+    (10..=14).contains(&bytes.len())
+        && bytes[..2].iter().all(u8::is_ascii_uppercase)
+        && bytes[2..].iter().all(u8::is_ascii_alphanumeric)
+        && bytes[2..].iter().filter(|b| b.is_ascii_digit()).count() >= 8
+}
+
+fn is_tax_word(word: &str) -> bool {
+    matches!(
+        word.trim_matches(|c: char| !c.is_ascii_alphanumeric()).to_ascii_lowercase().as_str(),
+        "tax" | "vat" | "ein" | "tin" | "gst" | "ust-idnr" | "steuernummer"
+    )
+}
+
+/// Remove tax numbers from a summary: values of tax fields in the event
+/// payload, VAT-shaped ids, and numbers following a tax keyword
+pub fn mask_summary(summary: &str, data: &Value) -> String {
+    let mut sensitive = Vec::new();
+    tax_values(data, false, &mut sensitive);
+    // Longest first so a value containing another is masked whole
+    sensitive.sort_by_key(|value| std::cmp::Reverse(value.len()));
+
+    let mut masked = summary.to_string();
+    for value in &sensitive {
+        masked = masked.replace(value.as_str(), MASK);
+    }
+
+    let mut words: Vec<String> = Vec::new();
+    let mut after_tax_word = false;
+    for word in masked.split(' ') {
+        let token = word.trim_matches(|c: char| !c.is_ascii_alphanumeric());
+        let digits = token.chars().filter(char::is_ascii_digit).count();
+        if !token.is_empty() && (looks_like_vat_id(token) || (after_tax_word && digits >= 5)) {
+            words.push(word.replacen(token, MASK, 1));
+        } else {
+            words.push(word.to_string());
+        }
+        // "VAT number DE..." and "tax id: 12-345..." skip one filler word
+        if is_tax_word(word) {
+            after_tax_word = true;
+        } else if !matches!(token.to_ascii_lowercase().as_str(), "number" | "no" | "id" | "nr" | "") {
+            after_tax_word = false;
+        }
+    }
+
+    let masked = words.join(" ");
+    match masked.char_indices().nth(MAX_SUMMARY_CHARS) {
+        Some((end, _)) => format!("{}…", &masked[..end]),
+        None => masked,
+    }
+}
+
+/// Sources, projected rows and marks
+#[async_trait]
+pub trait ActivityStore: Send + Sync {
+    async fn high_water_mark(&self, source: ActivitySource) -> Result<Option<HighWaterMark>>;
+
+    /// Up to `limit` events after `after` that are older than `settled_before`, in mark order
+    async fn read_source(
+        &self,
+        source: ActivitySource,
+        after: Option<&HighWaterMark>,
+        settled_before: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<SourceEvent>>;
+
+    /// Insert entries not projected before and move the source's mark, atomically;
+    /// returns rows inserted
+    async fn append(&self, source: ActivitySource, entries: &[ActivityEntry], mark: &HighWaterMark) -> Result<u64>;
+
+    /// Up to `limit` entries of the tenant matching `query`, newest first
+    async fn feed(&self, tenant_id: Uuid, query: &ActivityQuery, limit: i64) -> Result<Vec<ActivityEntry>>;
+
+    /// Entries of the tenant that occurred before `cutoff`
+    async fn count_before(&self, tenant_id: Uuid, cutoff: DateTime<Utc>) -> Result<u64>;
+
+    /// Remove up to `limit` of those; returns rows removed
+    async fn delete_before(&self, tenant_id: Uuid, cutoff: DateTime<Utc>, limit: i64) -> Result<u64>;
+}
+
+pub struct PostgresActivityStore {
+    pool: PgPool,
+}
+
+impl PostgresActivityStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+/// Each source selected into the same columns; `$1`/`$2` are the mark, `$3` the settle time
+fn source_query(source: ActivitySource) -> &'static str {
+    match source {
+        ActivitySource::Audit => {
+            "SELECT id AS source_id, created_at AS position, tenant_id, actor_id, event_type AS kind, \
+                 resource_type AS object_type, resource_id AS object_id, timestamp AS occurred_at, \
+                 severity, description, COALESCE(new_values, '{}'::JSONB) AS data \
+             FROM audit_events \
+             WHERE ($1::TIMESTAMPTZ IS NULL OR (created_at, id) > ($1, $2)) AND created_at < $3 \
+             ORDER BY created_at, id LIMIT $4"
+        }
+        ActivitySource::Customer => {
+            "SELECT event_id::TEXT AS source_id, recorded_at AS position, tenant_id::TEXT AS tenant_id, \
+                 user_id::TEXT AS actor_id, event_type AS kind, 'customer'::TEXT AS object_type, \
+                 aggregate_id::TEXT AS object_id, occurred_at, NULL::TEXT AS severity, \
+                 NULL::TEXT AS description, event_data AS data \
+             FROM customer_events \
+             WHERE ($1::TIMESTAMPTZ IS NULL OR (recorded_at, event_id::TEXT) > ($1, $2)) AND recorded_at < $3 \
+             ORDER BY recorded_at, event_id::TEXT LIMIT $4"
+        }
+        ActivitySource::Inventory => {
+            "SELECT t.id::TEXT AS source_id, t.created_at AS position, p.tenant_id::TEXT AS tenant_id, \
+                 t.created_by::TEXT AS actor_id, t.transaction_type::TEXT AS kind, 'product'::TEXT AS object_type, \
+                 t.product_id::TEXT AS object_id, t.transaction_date AS occurred_at, NULL::TEXT AS severity, \
+                 NULL::TEXT AS description, \
+                 jsonb_build_object('quantity_change', t.quantity_change, 'reference_number', t.reference_number, \
+                     'reason_code', t.reason_code, 'sku', p.sku, 'location_id', t.location_id) AS data \
+             FROM inventory_transactions t JOIN products p ON p.id = t.product_id \
+             WHERE ($1::TIMESTAMPTZ IS NULL OR (t.created_at, t.id::TEXT) > ($1, $2)) AND t.created_at < $3 \
+             ORDER BY t.created_at, t.id::TEXT LIMIT $4"
+        }
+    }
+}
+
+fn corrupt(what: &str, value: &str) -> Error {
+    Error::new(ErrorCode::DatabaseError, format!("Unknown activity {} '{}'", what, value))
+}
+
+/// `%`, `_` and `\` match literally in the free-text filter
+fn like_pattern(text: &str) -> String {
+    let escaped = text.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+    format!("%{}%", escaped)
+}
+
+#[async_trait]
+impl ActivityStore for PostgresActivityStore {
+    async fn high_water_mark(&self, source: ActivitySource) -> Result<Option<HighWaterMark>> {
+        let row = sqlx::query("SELECT position, source_id FROM public.activity_projection_marks WHERE source = $1")
+            .bind(source.as_str())
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(row.map(|row| HighWaterMark {
+            position: row.get("position"),
+            source_id: row.get("source_id"),
+        }))
+    }
+
+    async fn read_source(
+        &self,
+        source: ActivitySource,
+        after: Option<&HighWaterMark>,
+        settled_before: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<SourceEvent>> {
+        let rows = sqlx::query(source_query(source))
+            .bind(after.map(|mark| mark.position))
+            .bind(after.map(|mark| mark.source_id.clone()).unwrap_or_default())
+            .bind(settled_before)
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await?;
+
+        let uuid = |value: Option<String>| value.and_then(|v| v.parse::<Uuid>().ok());
+        Ok(rows
+            .iter()
+            .map(|row| SourceEvent {
+                source,
+                source_id: row.get("source_id"),
+                position: row.get("position"),
+                tenant_id: uuid(row.get("tenant_id")),
+                actor_id: uuid(row.get("actor_id")),
+                kind: row.get("kind"),
+                object_type: row.get("object_type"),
+                object_id: row.get("object_id"),
+                occurred_at: row.get("occurred_at"),
+                severity: row.get("severity"),
+                description: row.get("description"),
+                data: row.get("data"),
+            })
+            .collect())
+    }
+
+    async fn append(&self, source: ActivitySource, entries: &[ActivityEntry], mark: &HighWaterMark) -> Result<u64> {
+        let mut tx = self.pool.begin().await?;
+        let mut inserted = 0;
+        for entry in entries {
+            let result = sqlx::query(
+                "INSERT INTO public.tenant_activity \
+                 (tenant_id, source, source_id, actor_id, verb, object_type, object_id, summary, severity, occurred_at) \
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10) \
+                 ON CONFLICT (source, source_id) DO NOTHING",
+            )
+            .bind(entry.tenant_id)
+            .bind(entry.source.as_str())
+            .bind(&entry.source_id)
+            .bind(entry.actor_id)
+            .bind(&entry.verb)
+            .bind(&entry.object_type)
+            .bind(&entry.object_id)
+            .bind(&entry.summary)
+            .bind(entry.severity.as_str())
+            .bind(entry.occurred_at)
+            .execute(&mut *tx)
+            .await?;
+            inserted += result.rows_affected();
+        }
+
+        sqlx::query(
+            "INSERT INTO public.activity_projection_marks (source, position, source_id, updated_at) \
+             VALUES ($1, $2, $3, NOW()) \
+             ON CONFLICT (source) DO UPDATE SET \
+                 position = EXCLUDED.position, source_id = EXCLUDED.source_id, updated_at = NOW()",
+        )
+        .bind(source.as_str())
+        .bind(mark.position)
+        .bind(&mark.source_id)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(inserted)
+    }
+
+    async fn feed(&self, tenant_id: Uuid, query: &ActivityQuery, limit: i64) -> Result<Vec<ActivityEntry>> {
+        let rows = sqlx::query(
+            "SELECT id, tenant_id, source, source_id, actor_id, verb, object_type, object_id, summary, \
+                 severity, occurred_at \
+             FROM public.tenant_activity \
+             WHERE tenant_id = $1 \
+               AND (cardinality($2::TEXT[]) = 0 OR object_type = ANY($2)) \
+               AND ($3::UUID IS NULL OR actor_id = $3) \
+               AND ($4::TIMESTAMPTZ IS NULL OR occurred_at >= $4) \
+               AND ($5::TEXT IS NULL OR summary ILIKE $5) \
+               AND ($6::TIMESTAMPTZ IS NULL OR (occurred_at, id) < ($6, $7)) \
+             ORDER BY occurred_at DESC, id DESC LIMIT $8",
+        )
+        .bind(tenant_id)
+        .bind(&query.object_types)
+        .bind(query.actor_id)
+        .bind(query.since)
+        .bind(query.text.as_deref().map(like_pattern))
+        .bind(query.cursor.map(|c| c.occurred_at))
+        .bind(query.cursor.map_or(0, |c| c.id))
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                let source: String = row.get("source");
+                let severity: String = row.get("severity");
+                Ok(ActivityEntry {
+                    id: row.get("id"),
+                    tenant_id: row.get("tenant_id"),
+                    source: ActivitySource::parse(&source).ok_or_else(|| corrupt("source", &source))?,
+                    source_id: row.get("source_id"),
+                    actor_id: row.get("actor_id"),
+                    verb: row.get("verb"),
+                    object_type: row.get("object_type"),
+                    object_id: row.get("object_id"),
+                    summary: row.get("summary"),
+                    severity: ActivitySeverity::parse(&severity).ok_or_else(|| corrupt("severity", &severity))?,
+                    occurred_at: row.get("occurred_at"),
+                })
+            })
+            .collect()
+    }
+
+    async fn count_before(&self, tenant_id: Uuid, cutoff: DateTime<Utc>) -> Result<u64> {
+        let count: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM public.tenant_activity WHERE tenant_id = $1 AND occurred_at < $2")
+                .bind(tenant_id)
+                .bind(cutoff)
+                .fetch_one(&self.pool)
+                .await?;
+        Ok(count as u64)
+    }
+
+    async fn delete_before(&self, tenant_id: Uuid, cutoff: DateTime<Utc>, limit: i64) -> Result<u64> {
+        let result = sqlx::query(
+            "DELETE FROM public.tenant_activity WHERE id IN ( \
+                 SELECT id FROM public.tenant_activity WHERE tenant_id = $1 AND occurred_at < $2 \
+                 ORDER BY occurred_at LIMIT $3)",
+        )
+        .bind(tenant_id)
+        .bind(cutoff)
+        .bind(limit)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+}
+
+/// Old feed rows as the `tenant_activity` retention category
+pub struct ActivityRetention {
+    store: Arc<dyn ActivityStore>,
+}
+
+impl ActivityRetention {
+    pub fn category(store: Arc<dyn ActivityStore>, config: &ActivityConfig) -> RetentionCategory {
+        RetentionCategory {
+            name: "tenant_activity".to_string(),
+            description: "Activity feed entries by occurrence date; the sources keep their own history".to_string(),
+            default_days: config.retention_days.max(1),
+            default_action: RetentionAction::Delete,
+            supported_actions: vec![RetentionAction::Delete],
+            handler: Arc::new(Self { store }),
+        }
+    }
+}
+
+#[async_trait]
+impl RetentionHandler for ActivityRetention {
+    async fn count_expired(&self, tenant_id: Uuid, cutoff: DateTime<Utc>, _action: RetentionAction) -> Result<u64> {
+        self.store.count_before(tenant_id, cutoff).await
+    }
+
+    async fn enforce_batch(
+        &self,
+        tenant_id: Uuid,
+        cutoff: DateTime<Utc>,
+        _action: RetentionAction,
+        limit: i64,
+    ) -> Result<u64> {
+        self.store.delete_before(tenant_id, cutoff, limit).await
+    }
+}
+
+/// Projects the sources into the feed and serves feed pages
+pub struct ActivityService {
+    store: Arc<dyn ActivityStore>,
+    config: ActivityConfig,
+}
+
+impl ActivityService {
+    pub fn new(store: Arc<dyn ActivityStore>, config: ActivityConfig) -> Self {
+        Self { store, config }
+    }
+
+    /// Project everything `source` has after its mark; returns rows inserted
+    pub async fn project_source(&self, source: ActivitySource, now: DateTime<Utc>) -> Result<u64> {
+        let batch_size = self.config.batch_size.max(1);
+        let settled_before = now - Duration::seconds(SETTLE_SECS);
+        let mut mark = self.store.high_water_mark(source).await?;
+        let mut inserted = 0;
+
+        loop {
+            let events = self.store.read_source(source, mark.as_ref(), settled_before, batch_size).await?;
+            let Some(last) = events.last() else {
+                break;
+            };
+            let next = HighWaterMark {
+                position: last.position,
+                source_id: last.source_id.clone(),
+            };
+            let entries: Vec<ActivityEntry> = events.iter().filter_map(project).collect();
+            inserted += self.store.append(source, &entries, &next).await?;
+
+            if (events.len() as i64) < batch_size {
+                break;
+            }
+            mark = Some(next);
+        }
+        Ok(inserted)
+    }
+
+    /// Project every source; a failing source does not hold up the others
+    pub async fn project_all(&self, now: DateTime<Utc>) -> u64 {
+        let mut inserted = 0;
+        for source in ActivitySource::ALL {
+            match self.project_source(source, now).await {
+                Ok(rows) => inserted += rows,
+                Err(e) => warn!("Activity projection of {} events failed: {}", source.as_str(), e),
+            }
+        }
+        inserted
+    }
+
+    /// Page of the tenant's feed; `limit` defaults to `activity.page_size`
+    pub async fn feed(&self, tenant_id: Uuid, query: &ActivityQuery, limit: Option<i64>) -> Result<ActivityPage> {
+        let limit = limit
+            .unwrap_or(self.config.page_size)
+            .clamp(1, self.config.max_page_size.max(1));
+        let mut entries = self.store.feed(tenant_id, query, limit + 1).await?;
+
+        let has_more = entries.len() as i64 > limit;
+        entries.truncate(limit as usize);
+        let next_cursor = if has_more {
+            entries.last().map(|e| ActivityCursor::after(e).encode())
+        } else {
+            None
+        };
+
+        Ok(ActivityPage {
+            entries,
+            next_cursor,
+            has_more,
+        })
+    }
+
+    /// Every entry matching `query`, newest first, up to [`EXPORT_MAX_ROWS`];
+    /// the flag tells whether more entries matched
+    pub async fn export(&self, tenant_id: Uuid, query: &ActivityQuery) -> Result<(Vec<ActivityEntry>, bool)> {
+        let mut query = query.clone();
+        let mut entries = Vec::new();
+        loop {
+            let page = self.feed(tenant_id, &query, Some(self.config.max_page_size)).await?;
+            entries.extend(page.entries);
+            if entries.len() >= EXPORT_MAX_ROWS {
+                let truncated = entries.len() > EXPORT_MAX_ROWS || page.has_more;
+                entries.truncate(EXPORT_MAX_ROWS);
+                return Ok((entries, truncated));
+            }
+            match entries.last() {
+                Some(last) if page.has_more => query.cursor = Some(ActivityCursor::after(last)),
+                _ => return Ok((entries, false)),
+            }
+        }
+    }
+
+    /// Poll the sources every `activity.poll_interval_ms` in the background
+    pub fn spawn(self: &Arc<Self>) -> Option<tokio::task::JoinHandle<()>> {
+        if !self.config.enabled {
+            return None;
+        }
+
+        let service = Arc::clone(self);
+        Some(tokio::spawn(async move {
+            let mut ticker =
+                tokio::time::interval(std::time::Duration::from_millis(service.config.poll_interval_ms.max(100)));
+            loop {
+                ticker.tick().await;
+                let inserted = service.project_all(Utc::now()).await;
+                if inserted > 0 {
+                    info!("Projected {} activity entries", inserted);
+                }
+            }
+        }))
+    }
+}
+
+/// The audit export: header rows, then one line per entry
+pub fn export_csv(header: &ExportHeader, entries: &[ActivityEntry]) -> String {
+    let mut csv = header.csv_rows();
+    csv.push_str("occurred_at,source,severity,actor_id,verb,object_type,object_id,summary\n");
+    for entry in entries {
+        let fields = [
+            entry.occurred_at.to_rfc3339(),
+            entry.source.as_str().to_string(),
+            entry.severity.as_str().to_string(),
+            entry.actor_id.map(|id| id.to_string()).unwrap_or_default(),
+            csv_field(&entry.verb),
+            csv_field(&entry.object_type),
+            entry.object_id.as_deref().map(csv_field).unwrap_or_default(),
+            csv_field(&entry.summary),
+        ];
+        csv.push_str(&fields.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct MemoryStore {
+        sources: Mutex<HashMap<ActivitySource, Vec<SourceEvent>>>,
+        marks: Mutex<HashMap<ActivitySource, HighWaterMark>>,
+        rows: Mutex<Vec<ActivityEntry>>,
+    }
+
+    impl MemoryStore {
+        fn add(&self, event: SourceEvent) {
+            let mut sources = self.sources.lock().unwrap();
+            let events = sources.entry(event.source).or_default();
+            events.push(event);
+            events.sort_by(|a, b| (a.position, &a.source_id).cmp(&(b.position, &b.source_id)));
+        }
+
+        /// As if the projector crashed before any mark was written
+        fn forget_marks(&self) {
+            self.marks.lock().unwrap().clear();
+        }
+    }
+
+    fn matches(entry: &ActivityEntry, tenant_id: Uuid, query: &ActivityQuery) -> bool {
+        entry.tenant_id == tenant_id
+            && (query.object_types.is_empty() || query.object_types.contains(&entry.object_type))
+            && query.actor_id.is_none_or(|actor| entry.actor_id == Some(actor))
+            && query.since.is_none_or(|since| entry.occurred_at >= since)
+            && query
+                .text
+                .as_ref()
+                .is_none_or(|text| entry.summary.to_lowercase().contains(&text.to_lowercase()))
+            && query
+                .cursor
+                .is_none_or(|c| (entry.occurred_at, entry.id) < (c.occurred_at, c.id))
+    }
+
+    #[async_trait]
+    impl ActivityStore for MemoryStore {
+        async fn high_water_mark(&self, source: ActivitySource) -> Result<Option<HighWaterMark>> {
+            Ok(self.marks.lock().unwrap().get(&source).cloned())
+        }
+
+        async fn read_source(
+            &self,
+            source: ActivitySource,
+            after: Option<&HighWaterMark>,
+            settled_before: DateTime<Utc>,
+            limit: i64,
+        ) -> Result<Vec<SourceEvent>> {
+            let sources = self.sources.lock().unwrap();
+            Ok(sources
+                .get(&source)
+                .into_iter()
+                .flatten()
+                .filter(|e| after.is_none_or(|m| (e.position, &e.source_id) > (m.position, &m.source_id)))
+                .filter(|e| e.position < settled_before)
+                .take(limit as usize)
+                .cloned()
+                .collect())
+        }
+
+        async fn append(&self, source: ActivitySource, entries: &[ActivityEntry], mark: &HighWaterMark) -> Result<u64> {
+            let mut rows = self.rows.lock().unwrap();
+            let mut inserted = 0;
+            for entry in entries {
+                if rows.iter().any(|r| r.source == entry.source && r.source_id == entry.source_id) {
+                    continue;
+                }
+                let id = rows.len() as i64 + 1;
+                rows.push(ActivityEntry { id, ..entry.clone() });
+                inserted += 1;
+            }
+            self.marks.lock().unwrap().insert(source, mark.clone());
+            Ok(inserted)
+        }
+
+        async fn feed(&self, tenant_id: Uuid, query: &ActivityQuery, limit: i64) -> Result<Vec<ActivityEntry>> {
+            let mut entries: Vec<ActivityEntry> = self
+                .rows
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|e| matches(e, tenant_id, query))
+                .cloned()
+                .collect();
+            entries.sort_by_key(|e| std::cmp::Reverse((e.occurred_at, e.id)));
+            entries.truncate(limit as usize);
+            Ok(entries)
+        }
+
+        async fn count_before(&self, tenant_id: Uuid, cutoff: DateTime<Utc>) -> Result<u64> {
+            let rows = self.rows.lock().unwrap();
+            Ok(rows.iter().filter(|e| e.tenant_id == tenant_id && e.occurred_at < cutoff).count() as u64)
+        }
+
+        async fn delete_before(&self, tenant_id: Uuid, cutoff: DateTime<Utc>, _limit: i64) -> Result<u64> {
+            let mut rows = self.rows.lock().unwrap();
+            let before = rows.len();
+            rows.retain(|e| !(e.tenant_id == tenant_id && e.occurred_at < cutoff));
+            Ok((before - rows.len()) as u64)
+        }
+    }
+
+    fn event(source: ActivitySource, tenant_id: Uuid, minutes_ago: i64, kind: &str, data: Value) -> SourceEvent {
+        let at = Utc::now() - Duration::minutes(minutes_ago);
+        SourceEvent {
+            source,
+            source_id: Uuid::new_v4().to_string(),
+            position: at,
+            tenant_id: Some(tenant_id),
+            actor_id: Some(Uuid::new_v4()),
+            kind: kind.to_string(),
+            object_type: None,
+            object_id: Some(Uuid::new_v4().to_string()),
+            occurred_at: at,
+            severity: None,
+            description: None,
+            data,
+        }
+    }
+
+    fn audit(tenant_id: Uuid, minutes_ago: i64, description: &str) -> SourceEvent {
+        SourceEvent {
+            object_type: Some("user".to_string()),
+            severity: Some("Warning".to_string()),
+            description: Some(description.to_string()),
+            ..event(ActivitySource::Audit, tenant_id, minutes_ago, "PasswordChanged", json!({}))
+        }
+    }
+
+    /// As `customer_events.event_data` stores it
+    fn customer_event_data(event_type: &str, data: Value) -> Value {
+        json!({ "event_type": event_type, "data": data })
+    }
+
+    fn service(store: Arc<MemoryStore>, batch_size: i64) -> ActivityService {
+        let config = ActivityConfig {
+            batch_size,
+            page_size: 2,
+            ..Default::default()
+        };
+        ActivityService::new(store, config)
+    }
+
+    #[tokio::test]
+    async fn test_replay_after_crash_projects_nothing_twice() {
+        let tenant = Uuid::new_v4();
+        let store = Arc::new(MemoryStore::default());
+        for minutes_ago in [30, 20, 10] {
+            store.add(audit(tenant, minutes_ago, "Password changed"));
+            store.add(event(ActivitySource::Inventory, tenant, minutes_ago, "receipt", json!({"quantity_change": 5})));
+        }
+        // Platform events advance the mark but are not projected
+        store.add(SourceEvent { tenant_id: None, ..audit(tenant, 5, "System startup") });
+        let service = service(store.clone(), 2);
+
+        assert_eq!(service.project_all(Utc::now()).await, 6);
+        assert_eq!(service.project_all(Utc::now()).await, 0);
+
+        store.forget_marks();
+        assert_eq!(service.project_all(Utc::now()).await, 0);
+        assert_eq!(store.rows.lock().unwrap().len(), 6);
+
+        // New events resume from the mark
+        store.add(event(ActivitySource::Customer, tenant, 1, "customer_created", json!({})));
+        assert_eq!(service.project_all(Utc::now()).await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_unsettled_events_wait_for_the_next_poll() {
+        let tenant = Uuid::new_v4();
+        let store = Arc::new(MemoryStore::default());
+        let mut recent = audit(tenant, 0, "Password changed");
+        recent.position = Utc::now();
+        store.add(recent);
+        let service = service(store.clone(), 10);
+
+        assert_eq!(service.project_all(Utc::now()).await, 0);
+        assert_eq!(service.project_all(Utc::now() + Duration::seconds(SETTLE_SECS + 1)).await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_feed_interleaves_sources_newest_first() {
+        let (tenant, other) = (Uuid::new_v4(), Uuid::new_v4());
+        let store = Arc::new(MemoryStore::default());
+        store.add(audit(tenant, 50, "Password changed for j.doe"));
+        store.add(event(ActivitySource::Customer, tenant, 40, "customer_created", customer_event_data(
+            "CustomerCreated",
+            json!({"legal_name": "Acme GmbH", "customer_number": "C-1001"}),
+        )));
+        store.add(event(ActivitySource::Inventory, tenant, 30, "shipment", json!({"quantity_change": -3, "sku": "SKU-9"})));
+        store.add(audit(tenant, 20, "Role admin assigned"));
+        store.add(event(ActivitySource::Customer, tenant, 10, "credit_status_changed", json!({})));
+        store.add(audit(other, 15, "Other tenant"));
+        let service = service(store.clone(), 100);
+        service.project_all(Utc::now()).await;
+
+        let mut seen = Vec::new();
+        let mut query = ActivityQuery::default();
+        loop {
+            let page = service.feed(tenant, &query, None).await.unwrap();
+            assert!(page.entries.len() <= 2);
+            seen.extend(page.entries.iter().map(|e| e.source));
+            match page.next_cursor {
+                Some(cursor) => query.cursor = Some(ActivityCursor::decode(&cursor).unwrap()),
+                None => break,
+            }
+        }
+        assert_eq!(
+            seen,
+            vec![
+                ActivitySource::Customer,
+                ActivitySource::Audit,
+                ActivitySource::Inventory,
+                ActivitySource::Customer,
+                ActivitySource::Audit,
+            ]
+        );
+
+        let products = ActivityQuery { object_types: vec!["product".to_string()], ..Default::default() };
+        let page = service.feed(tenant, &products, None).await.unwrap();
+        assert_eq!(page.entries.len(), 1);
+        assert_eq!(page.entries[0].summary, "Shipment of -3 x SKU-9");
+
+        let text = ActivityQuery { text: Some("acme".to_string()), ..Default::default() };
+        let page = service.feed(tenant, &text, None).await.unwrap();
+        assert_eq!(page.entries[0].summary, "Customer created: Acme GmbH");
+        assert_eq!(page.entries[0].verb, "customer_created");
+
+        let page = service.feed(tenant, &ActivityQuery::default(), Some(10)).await.unwrap();
+        assert_eq!(page.entries[0].severity, ActivitySeverity::Warning);
+        assert_eq!(page.entries[1].verb, "password_changed");
+        assert!(!page.has_more && page.next_cursor.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_audit_export_carries_header_and_every_page() {
+        let tenant = Uuid::new_v4();
+        let store = Arc::new(MemoryStore::default());
+        for minutes_ago in [40, 30, 20, 10] {
+            store.add(audit(tenant, minutes_ago, "Role admin assigned, by ops"));
+        }
+        let service = ActivityService::new(store.clone(), ActivityConfig { max_page_size: 3, ..Default::default() });
+        service.project_all(Utc::now()).await;
+
+        let (entries, truncated) = service.export(tenant, &ActivityQuery::default()).await.unwrap();
+        assert_eq!((entries.len(), truncated), (4, false));
+
+        // A tenant without a letterhead still gets the provenance rows
+        let header = ExportHeader::new("Audit trail", None, "auditor").with_filter("types", "audit");
+        let csv = export_csv(&header, &entries);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "# Export: Audit trail");
+        assert!(lines[1].starts_with("# Generated at: "));
+        assert_eq!(lines[2..4], ["# Requested by: auditor", "# Filters: types=audit"]);
+        assert_eq!(lines[4], "occurred_at,source,severity,actor_id,verb,object_type,object_id,summary");
+        assert_eq!(lines.len(), 9);
+        assert!(lines[5].ends_with(",\"Role admin assigned, by ops\""));
+    }
+
+    #[test]
+    fn test_summaries_mask_tax_numbers() {
+        let tenant = Uuid::new_v4();
+        let updated = event(ActivitySource::Customer, tenant, 1, "customer_information_updated", customer_event_data(
+            "CustomerInformationUpdated",
+            json!({"new_legal_name": "Acme 4711-0815-99 GmbH", "tax_numbers": {"local": "4711-0815-99"}}),
+        ));
+        let entry = project(&updated).unwrap();
+        assert_eq!(entry.summary, "Customer information updated: Acme *** GmbH");
+
+        let described = audit(tenant, 1, "Changed VAT number DE123456789 and tax id: 12-3456789 of Acme");
+        let summary = project(&described).unwrap().summary;
+        assert_eq!(summary, "Changed VAT number *** and tax id: *** of Acme");
+
+        // Ordinary numbers and references stay readable
+        assert_eq!(mask_summary("Shipment of -12 x SKU-100200 (PO-2026-0042)", &json!({})), "Shipment of -12 x SKU-100200 (PO-2026-0042)");
+        assert_eq!(mask_summary("Buyer id GB987654321.", &json!({})), "Buyer id ***.");
+    }
+
+    #[test]
+    fn test_cursor_round_trips() {
+        let cursor = ActivityCursor {
+            occurred_at: "2026-10-16T08:30:00.123456Z".parse().unwrap(),
+            id: 42,
+        };
+        assert_eq!(ActivityCursor::decode(&cursor.encode()), Some(cursor));
+        assert_eq!(ActivityCursor::decode("not-a-cursor"), None);
+        assert_eq!(snake_case("AuthenticationSuccess"), "authentication_success");
+        assert_eq!(snake_case("CUSTOM_EXPORT"), "custom_export");
+    }
+}
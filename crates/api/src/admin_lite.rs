@@ -0,0 +1,677 @@
+//! # Admin Lite
+//!
+//! Break-glass admin pages for when the main frontend is down, served by the
+//! API itself under `/admin-lite` when `admin_lite.enabled` is set. Pages are
+//! rendered on the server from the same services the admin endpoints use; a
+//! small static script (`/admin-lite/static/admin-lite.js`) handles sign-in and
+//! calls the admin endpoints under `/api/v1/admin` for actions. Nothing is
+//! inlined, so the pages work under the API's strict Content-Security-Policy.
+//!
+//! Every request passes, in order:
+//!
+//! 1. [`allowlist_middleware`]: the client address must be in
+//!    `admin_lite.ip_allowlist`, otherwise 403
+//! 2. [`token_cookie_middleware`]: the access token stored by the sign-in page
+//!    in the [`TOKEN_COOKIE`] cookie becomes the `Authorization` header; 401
+//!    without any credentials
+//! 3. the regular `auth_middleware`
+//! 4. [`require_admin_middleware`]: 401 without an authenticated user, 403
+//!    without `system:admin`; attaches the [`Viewer`]
+//!
+//! The sign-in page and the static assets only pass the allowlist. Callers
+//! without `admin_lite.write_permission` get the pages read-only: no forms
+//! and no action buttons.
+
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{Html, IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use erp_core::retention::{RetentionAction, RetentionPolicy};
+use erp_core::{AdminLiteConfig, RequestContext};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::follow_up_reminders::html_escape;
+use crate::retention::RetentionEnforcement;
+use crate::tenant_health::{HealthStatus, TenantHealthReport, TenantHealthSummary};
+
+/// Cookie holding the access token of the signed-in operator
+pub const TOKEN_COOKIE: &str = "admin_lite_token";
+
+/// Permission required for every page
+pub const ADMIN_PERMISSION: &str = "system:admin";
+
+/// Addresses and CIDR ranges allowed to reach the pages
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IpAllowlist {
+    ranges: Vec<(IpAddr, u8)>,
+}
+
+impl IpAllowlist {
+    /// Entries are addresses (`10.0.0.5`) or ranges (`10.0.0.0/8`, `fd00::/8`)
+    pub fn parse(entries: &[String]) -> Result<Self, String> {
+        let ranges = entries
+            .iter()
+            .map(|entry| {
+                let (address, prefix) = match entry.trim().split_once('/') {
+                    Some((address, prefix)) => (address, Some(prefix)),
+                    None => (entry.trim(), None),
+                };
+                let address: IpAddr = address
+                    .parse()
+                    .map_err(|_| format!("Invalid address '{}' in admin_lite.ip_allowlist", entry))?;
+                let max = if address.is_ipv4() { 32 } else { 128 };
+                let prefix = match prefix {
+                    Some(prefix) => prefix
+                        .parse::<u8>()
+                        .ok()
+                        .filter(|prefix| *prefix <= max)
+                        .ok_or_else(|| format!("Invalid prefix length in admin_lite.ip_allowlist entry '{}'", entry))?,
+                    None => max,
+                };
+                Ok((address, prefix))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(Self { ranges })
+    }
+
+    pub fn allows(&self, ip: IpAddr) -> bool {
+        // An IPv4 client on a dual-stack socket shows up as ::ffff:a.b.c.d
+        let ip = ip.to_canonical();
+        self.ranges.iter().any(|(network, prefix)| match (network, ip) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - u32::from(*prefix)).unwrap_or(0);
+                u32::from(*network) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - u32::from(*prefix)).unwrap_or(0);
+                u128::from(*network) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        })
+    }
+}
+
+/// Settings shared by the admin lite middleware
+#[derive(Debug, Clone)]
+pub struct AdminLiteGuard {
+    allowlist: IpAllowlist,
+    trust_forwarded_for: bool,
+    write_permission: String,
+}
+
+impl AdminLiteGuard {
+    pub fn new(config: &AdminLiteConfig) -> Result<Self, String> {
+        Ok(Self {
+            allowlist: IpAllowlist::parse(&config.ip_allowlist)?,
+            trust_forwarded_for: config.trust_forwarded_for,
+            write_permission: config.write_permission.clone(),
+        })
+    }
+
+    fn client_ip(&self, request: &Request) -> Option<IpAddr> {
+        if self.trust_forwarded_for {
+            let forwarded = request
+                .headers()
+                .get("x-forwarded-for")
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.split(',').next())
+                .and_then(|first| first.trim().parse().ok());
+            if forwarded.is_some() {
+                return forwarded;
+            }
+        }
+        request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip())
+    }
+}
+
+/// The operator looking at the pages
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Viewer {
+    pub user_id: Option<Uuid>,
+    /// Holds `admin_lite.write_permission`; otherwise the pages are read-only
+    pub can_write: bool,
+    pub write_permission: String,
+}
+
+fn message_response(status: StatusCode, title: &str, message: &str) -> Response {
+    (status, Html(render_message(title, message))).into_response()
+}
+
+/// Reject clients outside `admin_lite.ip_allowlist`
+pub async fn allowlist_middleware(State(guard): State<Arc<AdminLiteGuard>>, request: Request, next: Next) -> Response {
+    match guard.client_ip(&request) {
+        Some(ip) if guard.allowlist.allows(ip) => next.run(request).await,
+        ip => {
+            warn!("Admin lite request from {:?} rejected by the IP allowlist", ip);
+            message_response(StatusCode::FORBIDDEN, "Forbidden", "Your address is not allowed to use these pages.")
+        }
+    }
+}
+
+fn token_from_cookies(request: &Request) -> Option<String> {
+    request
+        .headers()
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(name, value)| *name == TOKEN_COOKIE && !value.is_empty())
+        .map(|(_, value)| value.to_string())
+}
+
+/// Use the sign-in cookie as bearer token; an explicit `Authorization` header wins
+pub async fn token_cookie_middleware(mut request: Request, next: Next) -> Response {
+    if !request.headers().contains_key(header::AUTHORIZATION) {
+        let Some(token) = token_from_cookies(&request) else {
+            return message_response(StatusCode::UNAUTHORIZED, "Sign in required", "Sign in to use the admin pages.");
+        };
+        match HeaderValue::from_str(&format!("Bearer {}", token)) {
+            Ok(value) => {
+                request.headers_mut().insert(header::AUTHORIZATION, value);
+            }
+            Err(_) => {
+                return message_response(StatusCode::UNAUTHORIZED, "Sign in required", "Sign in to use the admin pages.");
+            }
+        }
+    }
+    next.run(request).await
+}
+
+/// Require an authenticated `system:admin` and attach the [`Viewer`]
+pub async fn require_admin_middleware(State(guard): State<Arc<AdminLiteGuard>>, mut request: Request, next: Next) -> Response {
+    let Some(context) = request.extensions().get::<RequestContext>() else {
+        return message_response(StatusCode::UNAUTHORIZED, "Sign in required", "Sign in to use the admin pages.");
+    };
+    if context.user_id.is_none() {
+        return message_response(StatusCode::UNAUTHORIZED, "Sign in required", "Sign in to use the admin pages.");
+    }
+    let holds = |permission: &str| context.permissions.iter().any(|p| p.to_string() == permission);
+    if !holds(ADMIN_PERMISSION) {
+        return message_response(
+            StatusCode::FORBIDDEN,
+            "Forbidden",
+            &format!("These pages require the {} permission.", ADMIN_PERMISSION),
+        );
+    }
+
+    let viewer = Viewer {
+        user_id: context.user_id,
+        can_write: holds(&guard.write_permission),
+        write_permission: guard.write_permission.clone(),
+    };
+    request.extensions_mut().insert(viewer);
+    next.run(request).await
+}
+
+/// Navigation entries
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Page {
+    Tenants,
+    Retention,
+}
+
+fn timestamp(value: DateTime<Utc>) -> String {
+    value.format("%Y-%m-%d %H:%M:%S UTC").to_string()
+}
+
+fn optional<T: ToString>(value: Option<T>) -> String {
+    value.map(|v| html_escape(&v.to_string())).unwrap_or_else(|| "–".to_string())
+}
+
+fn status_badge(status: HealthStatus) -> String {
+    format!(r#"<span class="badge badge-{0}">{0}</span>"#, status.as_str())
+}
+
+fn document(title: &str, nav: &str, body: &str) -> String {
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{title} · Admin Lite</title>
+<link rel="stylesheet" href="/admin-lite/static/admin-lite.css">
+<script src="/admin-lite/static/admin-lite.js" defer></script>
+</head>
+<body>
+<header><strong>Admin Lite</strong>{nav}</header>
+<main>
+<h1>{title}</h1>
+{body}
+</main>
+</body>
+</html>
+"#,
+        title = html_escape(title),
+        nav = nav,
+        body = body
+    )
+}
+
+/// Page with navigation; read-only pages say so at the top
+pub fn layout(title: &str, active: Page, viewer: &Viewer, body: &str) -> String {
+    let link = |page: Page, href: &str, label: &str| {
+        let class = if page == active { r#" class="active""# } else { "" };
+        format!(r#"<a href="{}"{}>{}</a>"#, href, class, label)
+    };
+    let nav = format!(
+        r#"<nav>{}{}<button type="button" data-action="sign-out">Sign out</button></nav>"#,
+        link(Page::Tenants, "/admin-lite/tenants", "Tenants"),
+        link(Page::Retention, "/admin-lite/retention", "Retention"),
+    );
+    let banner = if viewer.can_write {
+        String::new()
+    } else {
+        format!(
+            r#"<p class="read-only">Read-only: actions need the {} permission.</p>"#,
+            html_escape(&viewer.write_permission)
+        )
+    };
+    document(title, &nav, &format!("{}{}", banner, body))
+}
+
+/// Error or notice without navigation
+pub fn render_message(title: &str, message: &str) -> String {
+    document(
+        title,
+        "",
+        &format!(
+            r#"<p>{}</p><p><a href="/admin-lite/login">Sign in</a></p>"#,
+            html_escape(message)
+        ),
+    )
+}
+
+/// Sign-in form; the script exchanges the credentials for an access token
+pub fn render_login() -> String {
+    document(
+        "Sign in",
+        "",
+        r#"<form id="sign-in" data-action="sign-in">
+<label>Email <input type="email" name="email" autocomplete="username" required></label>
+<label>Password <input type="password" name="password" autocomplete="current-password" required></label>
+<button type="submit">Sign in</button>
+<p class="error" hidden></p>
+</form>"#,
+    )
+}
+
+/// Health and schema (migration) version of every tenant
+pub fn render_tenants(summary: &TenantHealthSummary, viewer: &Viewer) -> String {
+    let mut body = format!(
+        r#"<p>{} tenants, {} healthy, {} unhealthy. Checked {}{}.</p>"#,
+        summary.total,
+        summary.healthy,
+        summary.unhealthy,
+        timestamp(summary.generated_at),
+        if summary.cached { " (cached)" } else { "" }
+    );
+    if viewer.can_write {
+        body.push_str(r#"<p><button type="button" data-action="recheck" data-url="/api/v1/admin/tenants/health?refresh=true">Re-check all tenants</button></p>"#);
+    }
+
+    body.push_str("<table><thead><tr><th>Tenant</th><th>Schema</th><th>Status</th><th>Schema version</th><th>Active users (24h)</th><th>Last write</th></tr></thead><tbody>");
+    for tenant in &summary.tenants {
+        let version_class = if tenant.schema_version == Some(tenant.expected_schema_version) { "" } else { r#" class="behind""# };
+        body.push_str(&format!(
+            r#"<tr><td><a href="/admin-lite/tenants/{id}">{name}</a></td><td>{schema}</td><td>{status}</td><td{class}>{version} / {expected}</td><td>{users}</td><td>{last_write}</td></tr>"#,
+            id = tenant.tenant_id,
+            name = html_escape(&tenant.tenant_name),
+            schema = html_escape(&tenant.schema_name),
+            status = status_badge(tenant.status),
+            class = version_class,
+            version = optional(tenant.schema_version),
+            expected = tenant.expected_schema_version,
+            users = optional(tenant.active_users_24h),
+            last_write = optional(tenant.last_write_at.map(timestamp)),
+        ));
+    }
+    body.push_str("</tbody></table>");
+
+    layout("Tenants", Page::Tenants, viewer, &body)
+}
+
+/// Every check of one tenant with its latency
+pub fn render_tenant(report: &TenantHealthReport, viewer: &Viewer) -> String {
+    let mut body = format!(
+        r#"<dl><dt>Tenant</dt><dd>{id}</dd><dt>Schema</dt><dd>{schema}</dd><dt>Status</dt><dd>{status}</dd><dt>Schema version</dt><dd>{version} (expected {expected})</dd><dt>Checked</dt><dd>{checked}</dd></dl>"#,
+        id = report.tenant_id,
+        schema = html_escape(&report.schema_name),
+        status = status_badge(report.status),
+        version = optional(report.schema_version),
+        expected = report.expected_schema_version,
+        checked = timestamp(report.checked_at),
+    );
+    if !report.stuck_provisioning_steps.is_empty() {
+        body.push_str("<h2>Stuck provisioning steps</h2><ul>");
+        for step in &report.stuck_provisioning_steps {
+            body.push_str(&format!("<li>{}</li>", html_escape(step)));
+        }
+        body.push_str("</ul>");
+    }
+    if viewer.can_write {
+        body.push_str(&format!(
+            r#"<p><button type="button" data-action="recheck" data-url="/api/v1/admin/tenants/{}/health">Re-check</button></p>"#,
+            report.tenant_id
+        ));
+    }
+
+    body.push_str("<h2>Checks</h2><table><thead><tr><th>Check</th><th>Status</th><th>Latency</th><th>Detail</th></tr></thead><tbody>");
+    for check in &report.checks {
+        body.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td><td>{} ms</td><td>{}</td></tr>",
+            html_escape(&check.name),
+            status_badge(check.status),
+            check.latency_ms,
+            optional(check.detail.as_deref()),
+        ));
+    }
+    body.push_str("</tbody></table>");
+
+    layout(&report.tenant_name, Page::Tenants, viewer, &body)
+}
+
+/// A retention category with its global policy, if any
+#[derive(Debug, Clone)]
+pub struct RetentionRow {
+    pub category: String,
+    pub description: String,
+    pub default_days: i64,
+    pub default_action: RetentionAction,
+    pub supported_actions: Vec<RetentionAction>,
+    pub global_policy: Option<RetentionPolicy>,
+}
+
+/// Global retention policies per category and the latest enforcement runs
+pub fn render_retention(rows: &[RetentionRow], log: &[RetentionEnforcement], viewer: &Viewer) -> String {
+    let mut body = String::from("<h2>Global policies</h2><table><thead><tr><th>Category</th><th>Default</th><th>Global policy</th></tr></thead><tbody>");
+    for row in rows {
+        let policy = match &row.global_policy {
+            Some(policy) => format!("{} days, {}", policy.retention_days, policy.action.as_str()),
+            None => "–".to_string(),
+        };
+        let edit = if viewer.can_write {
+            let options: String = row
+                .supported_actions
+                .iter()
+                .map(|action| {
+                    let current = row.global_policy.as_ref().map(|p| p.action).unwrap_or(row.default_action);
+                    let selected = if *action == current { " selected" } else { "" };
+                    format!(r#"<option value="{0}"{1}>{0}</option>"#, action.as_str(), selected)
+                })
+                .collect();
+            let days = row.global_policy.as_ref().map(|p| p.retention_days).unwrap_or(row.default_days);
+            format!(
+                r#"<form data-action="retention-policy" data-category="{category}"><input type="number" name="retention_days" min="1" value="{days}" aria-label="Retention days"><select name="action" aria-label="Action">{options}</select><button type="submit">Save</button><span class="error" hidden></span></form>"#,
+                category = html_escape(&row.category),
+                days = days,
+                options = options,
+            )
+        } else {
+            String::new()
+        };
+        body.push_str(&format!(
+            r#"<tr><td><strong>{category}</strong><br><small>{description}</small></td><td>{default_days} days, {default_action}</td><td>{policy}{edit}</td></tr>"#,
+            category = html_escape(&row.category),
+            description = html_escape(&row.description),
+            default_days = row.default_days,
+            default_action = row.default_action.as_str(),
+            policy = policy,
+            edit = edit,
+        ));
+    }
+    body.push_str("</tbody></table>");
+
+    body.push_str("<h2>Recent runs</h2><table><thead><tr><th>Started</th><th>Tenant</th><th>Category</th><th>Action</th><th>Rows</th><th>Result</th></tr></thead><tbody>");
+    for entry in log {
+        let result = match (&entry.error, entry.completed) {
+            (Some(error), _) => format!("failed: {}", html_escape(error)),
+            (None, true) => "completed".to_string(),
+            (None, false) => "stopped at batch limit".to_string(),
+        };
+        body.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            timestamp(entry.started_at),
+            entry.tenant_id,
+            html_escape(&entry.category),
+            entry.action.as_str(),
+            entry.rows_affected,
+            result,
+        ));
+    }
+    body.push_str("</tbody></table>");
+
+    layout("Retention", Page::Retention, viewer, &body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tenant_health::CheckResult;
+    use axum::{body::Body, routing::get, Extension, Router};
+    use erp_core::retention::PolicySource;
+    use erp_core::Permission;
+    use tower::ServiceExt;
+
+    fn viewer(can_write: bool) -> Viewer {
+        Viewer {
+            user_id: Some(Uuid::new_v4()),
+            can_write,
+            write_permission: "system:maintenance".to_string(),
+        }
+    }
+
+    fn guard(allowlist: &[&str]) -> Arc<AdminLiteGuard> {
+        Arc::new(
+            AdminLiteGuard::new(&AdminLiteConfig {
+                ip_allowlist: allowlist.iter().map(|entry| entry.to_string()).collect(),
+                ..AdminLiteConfig::default()
+            })
+            .unwrap(),
+        )
+    }
+
+    fn context(permissions: &[&str]) -> RequestContext {
+        let permissions = permissions
+            .iter()
+            .map(|p| {
+                let (resource, action) = p.split_once(':').unwrap();
+                Permission::new(resource, action)
+            })
+            .collect();
+        RequestContext::new().with_user_id(Uuid::new_v4()).with_permissions(permissions)
+    }
+
+    fn report(name: &str, schema_version: Option<i64>, status: HealthStatus) -> TenantHealthReport {
+        TenantHealthReport {
+            tenant_id: Uuid::new_v4(),
+            tenant_name: name.to_string(),
+            schema_name: "tenant_acme".to_string(),
+            status,
+            schema_version,
+            expected_schema_version: 20,
+            sentinel_rows: Some(1),
+            last_write_at: Some(Utc::now()),
+            active_users_24h: Some(3),
+            stuck_provisioning_steps: Vec::new(),
+            checks: vec![CheckResult {
+                name: "schema_version".to_string(),
+                status,
+                latency_ms: 4,
+                detail: Some("19 < 20".to_string()),
+            }],
+            checked_at: Utc::now(),
+        }
+    }
+
+    /// Pages must not need inline scripts or styles under the strict CSP
+    fn assert_csp_compatible(html: &str) {
+        assert!(html.contains(r#"<script src="/admin-lite/static/admin-lite.js" defer></script>"#));
+        assert_eq!(html.matches("<script").count(), 1);
+        assert!(!html.contains("style=") && !html.contains("<style") && !html.contains("onclick"));
+    }
+
+    #[test]
+    fn test_allowlist_matches_addresses_and_ranges() {
+        let allowlist = IpAllowlist::parse(&["10.1.0.0/16".to_string(), "192.168.0.7".to_string(), "fd00::/8".to_string()]).unwrap();
+
+        assert!(allowlist.allows("10.1.200.3".parse().unwrap()));
+        assert!(!allowlist.allows("10.2.0.1".parse().unwrap()));
+        assert!(allowlist.allows("192.168.0.7".parse().unwrap()));
+        assert!(!allowlist.allows("192.168.0.8".parse().unwrap()));
+        assert!(allowlist.allows("fd12::1".parse().unwrap()));
+        assert!(allowlist.allows("::ffff:10.1.0.9".parse().unwrap()), "IPv4-mapped client");
+        assert!(IpAllowlist::parse(&["0.0.0.0/0".to_string()]).unwrap().allows("8.8.8.8".parse().unwrap()));
+
+        assert!(IpAllowlist::parse(&["10.0.0.0/33".to_string()]).is_err());
+        assert!(IpAllowlist::parse(&["intranet".to_string()]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_route_protection() {
+        let guard = guard(&["10.0.0.0/8"]);
+        let app = Router::new()
+            .route("/", get(|Extension(viewer): Extension<Viewer>| async move { viewer.can_write.to_string() }))
+            .layer(axum::middleware::from_fn_with_state(guard.clone(), require_admin_middleware))
+            .layer(axum::middleware::from_fn_with_state(guard, allowlist_middleware));
+        let call = |ip: &str, context: Option<RequestContext>| {
+            let mut request = Request::builder().uri("/").body(Body::empty()).unwrap();
+            request.extensions_mut().insert(ConnectInfo(SocketAddr::new(ip.parse().unwrap(), 40000)));
+            if let Some(context) = context {
+                request.extensions_mut().insert(context);
+            }
+            app.clone().oneshot(request)
+        };
+        let body = |response: Response| async move {
+            let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            String::from_utf8(bytes.to_vec()).unwrap()
+        };
+
+        let outside = call("203.0.113.9", Some(context(&["system:admin"]))).await.unwrap();
+        assert_eq!(outside.status(), StatusCode::FORBIDDEN);
+
+        let anonymous = call("10.0.0.4", None).await.unwrap();
+        assert_eq!(anonymous.status(), StatusCode::UNAUTHORIZED);
+
+        let clerk = call("10.0.0.4", Some(context(&["inventory:read"]))).await.unwrap();
+        assert_eq!(clerk.status(), StatusCode::FORBIDDEN);
+
+        let admin = call("10.0.0.4", Some(context(&["system:admin"]))).await.unwrap();
+        assert_eq!(admin.status(), StatusCode::OK);
+        assert_eq!(body(admin).await, "false");
+
+        let maintainer = call("10.0.0.4", Some(context(&["system:admin", "system:maintenance"]))).await.unwrap();
+        assert_eq!(body(maintainer).await, "true");
+    }
+
+    #[tokio::test]
+    async fn test_token_cookie_becomes_bearer_token() {
+        let app = Router::new()
+            .route(
+                "/",
+                get(|request: Request| async move {
+                    request.headers().get(header::AUTHORIZATION).unwrap().to_str().unwrap().to_string()
+                }),
+            )
+            .layer(axum::middleware::from_fn(token_cookie_middleware));
+
+        let request = Request::builder()
+            .uri("/")
+            .header(header::COOKIE, format!("theme=dark; {}=abc.def", TOKEN_COOKIE))
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&bytes[..], b"Bearer abc.def");
+
+        let request = Request::builder().uri("/").body(Body::empty()).unwrap();
+        assert_eq!(app.oneshot(request).await.unwrap().status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[test]
+    fn test_tenant_pages_render_and_degrade_to_read_only() {
+        let summary = TenantHealthSummary {
+            total: 2,
+            healthy: 1,
+            unhealthy: 1,
+            cached: true,
+            generated_at: Utc::now(),
+            tenants: vec![
+                report("Acme <Corp>", Some(19), HealthStatus::Degraded),
+                report("Globex", Some(20), HealthStatus::Ok),
+            ],
+        };
+
+        let html = render_tenants(&summary, &viewer(false));
+        assert_csp_compatible(&html);
+        assert!(html.contains("Acme &lt;Corp&gt;"), "names are escaped");
+        assert!(html.contains(r#"<td class="behind">19 / 20</td>"#));
+        assert!(html.contains(&format!("/admin-lite/tenants/{}", summary.tenants[1].tenant_id)));
+        assert!(html.contains("Read-only"));
+        assert!(!html.contains(r#"data-action="recheck""#));
+        assert!(render_tenants(&summary, &viewer(true)).contains(r#"data-action="recheck""#));
+
+        let detail = render_tenant(&summary.tenants[0], &viewer(true));
+        assert_csp_compatible(&detail);
+        assert!(detail.contains("19 (expected 20)"));
+        assert!(detail.contains("<td>schema_version</td>"));
+        assert!(!detail.contains("Read-only"));
+    }
+
+    #[test]
+    fn test_retention_page_renders_and_degrades_to_read_only() {
+        let rows = vec![RetentionRow {
+            category: "audit_events".to_string(),
+            description: "Audit trail".to_string(),
+            default_days: 2555,
+            default_action: RetentionAction::Archive,
+            supported_actions: vec![RetentionAction::Delete, RetentionAction::Archive],
+            global_policy: Some(RetentionPolicy {
+                tenant_id: None,
+                category: "audit_events".to_string(),
+                retention_days: 3000,
+                action: RetentionAction::Delete,
+                updated_by: None,
+                updated_at: Utc::now(),
+            }),
+        }];
+        let log = vec![RetentionEnforcement {
+            id: Uuid::new_v4(),
+            tenant_id: Uuid::new_v4(),
+            category: "audit_events".to_string(),
+            action: RetentionAction::Delete,
+            retention_days: 3000,
+            policy_source: PolicySource::Global,
+            cutoff: Utc::now(),
+            rows_affected: 42,
+            batches: 1,
+            completed: false,
+            error: None,
+            started_at: Utc::now(),
+            finished_at: Utc::now(),
+        }];
+
+        let read_only = render_retention(&rows, &log, &viewer(false));
+        assert_csp_compatible(&read_only);
+        assert!(read_only.contains("3000 days, delete"));
+        assert!(read_only.contains("stopped at batch limit"));
+        assert!(!read_only.contains("<form"));
+
+        let writable = render_retention(&rows, &log, &viewer(true));
+        assert!(writable.contains(r#"data-category="audit_events""#));
+        assert!(writable.contains(r#"<option value="delete" selected>delete</option>"#));
+
+        assert_csp_compatible(&render_login());
+        assert_csp_compatible(&render_message("Forbidden", "No <access>"));
+    }
+}
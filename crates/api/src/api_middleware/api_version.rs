@@ -0,0 +1,299 @@
+//! # API Version Negotiation Middleware
+//!
+//! Clients pick a response schema version with the `X-API-Version` request
+//! header; without it they get the latest version. The resolved [`ApiVersion`]
+//! is inserted as a request extension so handlers can choose the matching
+//! response DTOs (see [`crate::responses`]).
+//!
+//! ## Behaviour
+//!
+//! - Unknown versions are rejected with `400 Bad Request` listing the supported versions
+//! - Every response echoes the resolved version in `X-API-Version`
+//! - Versions scheduled for removal in `api_versioning.deprecations` get
+//!   `Deprecation` (RFC 9745), `Sunset` (RFC 8594) and optionally a
+//!   `Link: <...>; rel="deprecation"` header
+//! - Usage is counted per tenant and version so we know when a version can be dropped
+
+use axum::{
+    extract::{Request, State},
+    http::{header::HeaderValue, HeaderName, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{NaiveDate, TimeZone, Utc};
+use erp_core::{ApiVersioningConfig, TenantContext};
+use prometheus::{IntCounterVec, Opts};
+use serde_json::json;
+use std::{collections::HashMap, sync::Arc};
+
+/// Header carrying the requested (and resolved) API version
+pub const API_VERSION_HEADER: &str = "x-api-version";
+
+/// Response schema versions understood by this server
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ApiVersion {
+    /// Original shapes: domain models as stored, prices as integer cents
+    V1,
+    /// Explicit response DTOs with `Money` objects for monetary fields
+    V2,
+}
+
+impl ApiVersion {
+    pub const SUPPORTED: [ApiVersion; 2] = [ApiVersion::V1, ApiVersion::V2];
+    pub const LATEST: ApiVersion = ApiVersion::V2;
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ApiVersion::V1 => "1",
+            ApiVersion::V2 => "2",
+        }
+    }
+
+    /// Accepts `2` as well as `v2`/`V2`
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        let value = value.strip_prefix(['v', 'V']).unwrap_or(value);
+        Self::SUPPORTED.into_iter().find(|v| v.as_str() == value)
+    }
+}
+
+impl Default for ApiVersion {
+    fn default() -> Self {
+        Self::LATEST
+    }
+}
+
+#[derive(Debug, Clone)]
+struct DeprecationHeaders {
+    deprecation: HeaderValue,
+    sunset: HeaderValue,
+    link: Option<HeaderValue>,
+}
+
+/// Deprecation schedule and usage metrics shared by all requests
+pub struct ApiVersionPolicy {
+    deprecations: HashMap<ApiVersion, DeprecationHeaders>,
+    usage: IntCounterVec,
+}
+
+impl ApiVersionPolicy {
+    pub fn new(config: &ApiVersioningConfig, namespace: &str) -> Result<Self, String> {
+        let mut deprecations = HashMap::new();
+        for entry in &config.deprecations {
+            let version = ApiVersion::parse(&entry.version)
+                .ok_or_else(|| format!("Unknown API version in api_versioning.deprecations: {}", entry.version))?;
+            if version == ApiVersion::LATEST {
+                return Err(format!("The latest API version ({}) cannot be deprecated", version.as_str()));
+            }
+            if entry.sunset_on <= entry.deprecated_on {
+                return Err(format!("Sunset date for API version {} must be after its deprecation date", entry.version));
+            }
+
+            let link = entry
+                .link
+                .as_ref()
+                .map(|url| HeaderValue::from_str(&format!("<{}>; rel=\"deprecation\"", url)))
+                .transpose()
+                .map_err(|e| format!("Invalid deprecation link for API version {}: {}", entry.version, e))?;
+
+            deprecations.insert(
+                version,
+                DeprecationHeaders {
+                    deprecation: header_value(format!("@{}", midnight_utc(entry.deprecated_on).timestamp())),
+                    sunset: header_value(midnight_utc(entry.sunset_on).format("%a, %d %b %Y %H:%M:%S GMT").to_string()),
+                    link,
+                },
+            );
+        }
+
+        let usage = IntCounterVec::new(
+            Opts::new(
+                format!("{}_api_version_requests_total", namespace),
+                "Requests per tenant and API response version",
+            ),
+            &["tenant_id", "version"],
+        )
+        .map_err(|e| e.to_string())?;
+
+        Ok(Self { deprecations, usage })
+    }
+
+    /// Counter to register with the metrics registry
+    pub fn usage_counter(&self) -> IntCounterVec {
+        self.usage.clone()
+    }
+}
+
+fn midnight_utc(date: NaiveDate) -> chrono::DateTime<Utc> {
+    Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0).unwrap_or_default())
+}
+
+fn header_value(value: String) -> HeaderValue {
+    // Built from digits and ASCII date formatting only
+    HeaderValue::from_str(&value).unwrap_or_else(|_| HeaderValue::from_static(""))
+}
+
+/// Resolve `X-API-Version`, expose it to handlers and decorate the response
+pub async fn api_version_middleware(
+    State(policy): State<Arc<ApiVersionPolicy>>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    let requested = req
+        .headers()
+        .get(API_VERSION_HEADER)
+        .map(|v| v.to_str().unwrap_or_default().to_string());
+
+    let version = match requested {
+        None => ApiVersion::LATEST,
+        Some(raw) => match ApiVersion::parse(&raw) {
+            Some(version) => version,
+            None => {
+                let supported: Vec<&str> = ApiVersion::SUPPORTED.iter().map(|v| v.as_str()).collect();
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(json!({
+                        "error": "Unsupported API version",
+                        "requested_version": raw,
+                        "supported_versions": supported,
+                        "latest_version": ApiVersion::LATEST.as_str()
+                    })),
+                )
+                    .into_response();
+            }
+        },
+    };
+
+    let tenant = req
+        .extensions()
+        .get::<TenantContext>()
+        .map(|ctx| ctx.tenant_id.0.to_string())
+        .unwrap_or_else(|| "none".to_string());
+    policy.usage.with_label_values(&[tenant.as_str(), version.as_str()]).inc();
+
+    req.extensions_mut().insert(version);
+    let mut response = next.run(req).await;
+
+    let headers = response.headers_mut();
+    headers.insert(HeaderName::from_static(API_VERSION_HEADER), HeaderValue::from_static(version.as_str()));
+    if let Some(deprecation) = policy.deprecations.get(&version) {
+        headers.insert(HeaderName::from_static("deprecation"), deprecation.deprecation.clone());
+        headers.insert(HeaderName::from_static("sunset"), deprecation.sunset.clone());
+        if let Some(link) = &deprecation.link {
+            headers.append(axum::http::header::LINK, link.clone());
+        }
+    }
+
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, extract::Extension, routing::get, Router};
+    use erp_core::ApiVersionDeprecation;
+    use tower::ServiceExt;
+
+    fn policy(deprecations: Vec<ApiVersionDeprecation>) -> Arc<ApiVersionPolicy> {
+        Arc::new(ApiVersionPolicy::new(&ApiVersioningConfig { deprecations }, "test").unwrap())
+    }
+
+    fn v1_deprecation() -> ApiVersionDeprecation {
+        ApiVersionDeprecation {
+            version: "1".to_string(),
+            deprecated_on: NaiveDate::from_ymd_opt(2026, 9, 1).unwrap(),
+            sunset_on: NaiveDate::from_ymd_opt(2027, 3, 1).unwrap(),
+            link: Some("https://docs.example.com/api/v2".to_string()),
+        }
+    }
+
+    fn app(policy: Arc<ApiVersionPolicy>) -> Router {
+        Router::new()
+            .route("/", get(|Extension(version): Extension<ApiVersion>| async move { version.as_str() }))
+            .layer(axum::middleware::from_fn_with_state(policy, api_version_middleware))
+    }
+
+    async fn call(policy: Arc<ApiVersionPolicy>, version: Option<&str>) -> Response {
+        let mut request = Request::builder().uri("/");
+        if let Some(version) = version {
+            request = request.header(API_VERSION_HEADER, version);
+        }
+        app(policy).oneshot(request.body(Body::empty()).unwrap()).await.unwrap()
+    }
+
+    async fn body_string(response: Response) -> String {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    #[test]
+    fn test_parse_versions() {
+        assert_eq!(ApiVersion::parse("1"), Some(ApiVersion::V1));
+        assert_eq!(ApiVersion::parse("v2"), Some(ApiVersion::V2));
+        assert_eq!(ApiVersion::parse("V1"), Some(ApiVersion::V1));
+        assert_eq!(ApiVersion::parse("3"), None);
+        assert_eq!(ApiVersion::parse(""), None);
+    }
+
+    #[tokio::test]
+    async fn test_missing_header_defaults_to_latest() {
+        let response = call(policy(vec![v1_deprecation()]), None).await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[API_VERSION_HEADER], ApiVersion::LATEST.as_str());
+        assert!(response.headers().get("deprecation").is_none());
+        assert!(response.headers().get("sunset").is_none());
+        assert_eq!(body_string(response).await, ApiVersion::LATEST.as_str());
+    }
+
+    #[tokio::test]
+    async fn test_deprecated_version_gets_deprecation_headers() {
+        let response = call(policy(vec![v1_deprecation()]), Some("1")).await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let headers = response.headers();
+        assert_eq!(headers[API_VERSION_HEADER], "1");
+        // 2026-09-01T00:00:00Z
+        assert_eq!(headers["deprecation"], "@1788220800");
+        assert_eq!(headers["sunset"], "Mon, 01 Mar 2027 00:00:00 GMT");
+        assert_eq!(headers["link"], "<https://docs.example.com/api/v2>; rel=\"deprecation\"");
+        assert_eq!(body_string(response).await, "1");
+    }
+
+    #[tokio::test]
+    async fn test_version_without_schedule_has_no_deprecation_headers() {
+        let response = call(policy(vec![]), Some("1")).await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers().get("deprecation").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_unknown_version_is_rejected_with_supported_list() {
+        let response = call(policy(vec![]), Some("7")).await;
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body: serde_json::Value = serde_json::from_str(&body_string(response).await).unwrap();
+        assert_eq!(body["supported_versions"], json!(["1", "2"]));
+        assert_eq!(body["requested_version"], "7");
+    }
+
+    #[tokio::test]
+    async fn test_usage_is_counted_per_version() {
+        let policy = policy(vec![]);
+        call(policy.clone(), Some("1")).await;
+        call(policy.clone(), Some("1")).await;
+        call(policy.clone(), None).await;
+
+        assert_eq!(policy.usage.with_label_values(&["none", "1"]).get(), 2);
+        assert_eq!(policy.usage.with_label_values(&["none", "2"]).get(), 1);
+    }
+
+    #[test]
+    fn test_latest_version_cannot_be_deprecated() {
+        let mut deprecation = v1_deprecation();
+        deprecation.version = "2".to_string();
+        assert!(ApiVersionPolicy::new(&ApiVersioningConfig { deprecations: vec![deprecation] }, "test").is_err());
+    }
+}
@@ -0,0 +1,70 @@
+//! # Request Metrics Middleware
+//!
+//! Times every routed request and records it in [`HttpMetrics`] under its
+//! method, route template and status. The template comes from axum's
+//! [`MatchedPath`], so `/api/v1/customers/:id` is one series however many
+//! customers are requested; requests no route matched are left out.
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    middleware::Next,
+    response::Response,
+};
+use erp_core::HttpMetrics;
+use std::time::Instant;
+
+pub async fn http_metrics_middleware(
+    State(metrics): State<HttpMetrics>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let Some(route) = req.extensions().get::<MatchedPath>().map(|path| path.as_str().to_owned()) else {
+        return next.run(req).await;
+    };
+    let method = req.method().clone();
+    let started = Instant::now();
+
+    let response = next.run(req).await;
+    metrics.observe(
+        method.as_str(),
+        &route,
+        response.status().as_u16(),
+        started.elapsed().as_secs_f64(),
+    );
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::StatusCode, routing::get, Router};
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_requests_are_recorded_under_their_route_template() {
+        let metrics = HttpMetrics::new("test").unwrap();
+        let app = Router::new()
+            .nest(
+                "/api/v1",
+                Router::new().route("/customers/:id", get(|| async { StatusCode::NOT_FOUND })),
+            )
+            .layer(axum::middleware::from_fn_with_state(metrics.clone(), http_metrics_middleware));
+
+        for id in ["1", "2"] {
+            let uri = format!("/api/v1/customers/{}", id);
+            app.clone()
+                .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(
+            metrics
+                .request_duration_seconds
+                .with_label_values(&["GET", "/api/v1/customers/:id", "404"])
+                .get_sample_count(),
+            2
+        );
+        assert_eq!(metrics.client_errors_total.with_label_values(&["GET", "/api/v1/customers/:id"]).get(), 2);
+    }
+}
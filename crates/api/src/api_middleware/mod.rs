@@ -1,4 +1,9 @@
+pub mod api_version;
+pub mod http_metrics;
+pub mod rate_limit;
 pub mod request_id;
+pub mod sandbox;
 pub mod security_headers;
 pub mod tenant_context;
 
+pub mod warnings;
@@ -0,0 +1,573 @@
+//! # Sliding-Window Rate Limits
+//!
+//! [`rate_limit_middleware`] runs around every `/api/v1` route and counts
+//! each call in a sliding window per caller and route class:
+//!
+//! - [`RouteBucket::Route`]: a path with a limit of its own in
+//!   `rate_limit.routes`, such as `/auth/login` and `/auth/forgot-password`
+//! - [`RouteBucket::AuthLogin`]: the other `/auth` endpoints (2FA,
+//!   registration, password reset), limited by `rate_limit.auth_login`
+//! - [`RouteBucket::ApiDefault`]: everything else, limited by
+//!   `rate_limit.api_default`
+//!
+//! The caller is the tenant and user of a valid access token, or before
+//! sign-in the tenant named by the request (`X-Tenant-ID`) and the client
+//! address; a request that names no tenant is limited by its address alone.
+//! Windows are Redis sorted sets under
+//! `rate_limit:window:[{tenant}:]{caller}:{class}` holding the calls of the
+//! last window by time; a refused call is not kept, so a client that keeps
+//! retrying gets in again once its window has room.
+//!
+//! A call over the limit is answered 429 with `Retry-After` at the time the
+//! oldest call leaves the window; every response carries `X-RateLimit-*`
+//! headers of the tighter of this window and the per-token limit of
+//! [`crate::api_usage`]. If Redis cannot be reached the call is let through
+//! without rate-limit headers rather than refused.
+//!
+//! `{namespace}_rate_limit_requests_total{class, outcome}` counts the calls
+//! let through (`allowed`) and refused (`limited`) per route class,
+//! `{namespace}_rate_limit_unavailable_total` the calls let through unchecked.
+
+use async_trait::async_trait;
+use axum::{
+    extract::{ConnectInfo, Extension, Request},
+    http::header,
+    middleware::Next,
+    response::Response,
+};
+use erp_core::api_usage::RateLimitStanding;
+use erp_core::config::RateLimitConfig;
+use erp_core::security::JwtService;
+use erp_core::{MetricsRegistry, Result, TenantContext};
+use prometheus::{IntCounter, IntCounterVec, Opts};
+use redis::aio::ConnectionManager;
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::api_usage::{rate_limited, set_headers};
+
+/// Prefix of the sliding windows in Redis
+pub const RATE_LIMIT_PREFIX: &str = "rate_limit:window";
+
+/// Routes that share a limit
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RouteBucket {
+    /// A path of `rate_limit.routes`, limited on its own
+    Route(String),
+    AuthLogin,
+    ApiDefault,
+}
+
+impl RouteBucket {
+    /// Bucket of a path below `/api/v1` without a limit of its own
+    pub fn of(path: &str) -> Self {
+        let path = route_path(path);
+        if path == "/auth" || path.starts_with("/auth/") {
+            RouteBucket::AuthLogin
+        } else {
+            RouteBucket::ApiDefault
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            RouteBucket::Route(path) => path,
+            RouteBucket::AuthLogin => "auth_login",
+            RouteBucket::ApiDefault => "api_default",
+        }
+    }
+}
+
+/// The path below `/api/v1`, without a trailing slash
+fn route_path(path: &str) -> &str {
+    let path = path.strip_prefix("/api/v1").unwrap_or(path);
+    match path.trim_end_matches('/') {
+        "" => "/",
+        path => path,
+    }
+}
+
+/// At most `limit` calls in any `window`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimitRule {
+    pub limit: u32,
+    pub window: Duration,
+}
+
+impl RateLimitRule {
+    /// `<calls>/<window>` with a window of `second`, `minute` or `hour`,
+    /// optionally counted: `10/minute`, `3/15minutes`
+    pub fn parse(value: &str) -> std::result::Result<Self, String> {
+        let invalid = || {
+            format!(
+                "Invalid rate limit '{}', expected <calls>/[<count>]<second|minute|hour>",
+                value
+            )
+        };
+        let (limit, window) = value.split_once('/').ok_or_else(invalid)?;
+        let limit: u32 = limit.trim().parse().map_err(|_| invalid())?;
+        let window = window.trim();
+        let unit_at = window.find(|c: char| !c.is_ascii_digit()).ok_or_else(invalid)?;
+        let count: u64 = match &window[..unit_at] {
+            "" => 1,
+            count => count.parse().map_err(|_| invalid())?,
+        };
+        let unit_secs = match window[unit_at..].trim() {
+            "second" | "seconds" | "s" => 1,
+            "minute" | "minutes" | "m" => 60,
+            "hour" | "hours" | "h" => 3600,
+            _ => return Err(invalid()),
+        };
+        if limit == 0 || count == 0 {
+            return Err(invalid());
+        }
+        Ok(Self { limit, window: Duration::from_secs(count * unit_secs) })
+    }
+}
+
+/// Calls of a caller in the last window
+#[async_trait]
+pub trait SlidingWindow: Send + Sync {
+    /// Count a call at `now_ms` unless the window is full; the calls in the
+    /// window including this one, and milliseconds until the oldest leaves it
+    async fn hit(&self, key: &str, rule: &RateLimitRule, now_ms: u64) -> Result<(u64, u64)>;
+}
+
+/// Sliding windows as Redis sorted sets scored by call time
+pub struct RedisSlidingWindow {
+    redis: ConnectionManager,
+}
+
+impl RedisSlidingWindow {
+    pub fn new(redis: ConnectionManager) -> Self {
+        Self { redis }
+    }
+}
+
+#[async_trait]
+impl SlidingWindow for RedisSlidingWindow {
+    async fn hit(&self, key: &str, rule: &RateLimitRule, now_ms: u64) -> Result<(u64, u64)> {
+        let window_ms = rule.window.as_millis() as u64;
+        let member = format!("{}:{}", now_ms, Uuid::new_v4().simple());
+        let mut redis = self.redis.clone();
+        let (count, oldest): (u64, Vec<(String, f64)>) = redis::pipe()
+            .atomic()
+            .cmd("ZREMRANGEBYSCORE").arg(key).arg(0).arg(now_ms.saturating_sub(window_ms)).ignore()
+            .cmd("ZADD").arg(key).arg(now_ms).arg(&member).ignore()
+            .cmd("ZCARD").arg(key)
+            .cmd("ZRANGE").arg(key).arg(0).arg(0).arg("WITHSCORES")
+            .cmd("PEXPIRE").arg(key).arg(window_ms).ignore()
+            .query_async(&mut redis)
+            .await?;
+        if count > u64::from(rule.limit) {
+            // Refused calls do not hold the window shut
+            let _: i64 = redis::cmd("ZREM").arg(key).arg(&member).query_async(&mut redis).await?;
+        }
+        let oldest = oldest.first().map_or(now_ms, |(_, at)| *at as u64);
+        Ok((count, (oldest + window_ms).saturating_sub(now_ms)))
+    }
+}
+
+pub struct RateLimiter {
+    window: Arc<dyn SlidingWindow>,
+    jwt: Arc<JwtService>,
+    routes: BTreeMap<String, RateLimitRule>,
+    auth_login: RateLimitRule,
+    api_default: RateLimitRule,
+    trust_forwarded_for: bool,
+    requests: IntCounterVec,
+    unavailable: IntCounter,
+}
+
+impl RateLimiter {
+    /// Fails on limits that do not parse, so a typo stops the start
+    pub fn new(
+        window: Arc<dyn SlidingWindow>,
+        jwt: Arc<JwtService>,
+        config: &RateLimitConfig,
+        namespace: &str,
+    ) -> std::result::Result<Self, String> {
+        let routes = config
+            .routes
+            .iter()
+            .map(|(path, limit)| {
+                let rule = RateLimitRule::parse(limit).map_err(|e| format!("rate_limit.routes.\"{}\": {}", path, e))?;
+                Ok((route_path(path).to_string(), rule))
+            })
+            .collect::<std::result::Result<_, String>>()?;
+        Ok(Self {
+            window,
+            jwt,
+            routes,
+            auth_login: RateLimitRule::parse(&config.auth_login)?,
+            api_default: RateLimitRule::parse(&config.api_default)?,
+            trust_forwarded_for: config.trust_forwarded_for,
+            requests: IntCounterVec::new(
+                Opts::new(
+                    format!("{}_rate_limit_requests_total", namespace),
+                    "Calls counted by the sliding-window rate limits, allowed or limited, per route class",
+                ),
+                &["class", "outcome"],
+            )
+            .map_err(|e| e.to_string())?,
+            unavailable: IntCounter::new(
+                format!("{}_rate_limit_unavailable_total", namespace),
+                "Calls let through unchecked because the rate limit windows could not be reached",
+            )
+            .map_err(|e| e.to_string())?,
+        })
+    }
+
+    pub fn register(&self, metrics: &MetricsRegistry) -> std::result::Result<(), prometheus::Error> {
+        metrics.register(self.requests.clone())?;
+        metrics.register(self.unavailable.clone())?;
+        Ok(())
+    }
+
+    /// Route class of a path below `/api/v1`
+    pub fn bucket(&self, path: &str) -> RouteBucket {
+        let path = route_path(path);
+        match self.routes.contains_key(path) {
+            true => RouteBucket::Route(path.to_string()),
+            false => RouteBucket::of(path),
+        }
+    }
+
+    pub fn rule(&self, bucket: &RouteBucket) -> RateLimitRule {
+        match bucket {
+            RouteBucket::Route(path) => self.routes.get(path).copied().unwrap_or(self.api_default),
+            RouteBucket::AuthLogin => self.auth_login,
+            RouteBucket::ApiDefault => self.api_default,
+        }
+    }
+
+    /// `[{tenant}:]{caller}`: tenant and user of a valid token, otherwise the
+    /// tenant named by the request, if any, and the client address
+    fn caller(&self, req: &Request) -> String {
+        let claims = req
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .and_then(|token| self.jwt.verify_access_token(token).ok());
+        if let Some(claims) = claims {
+            return format!("{}:user:{}", claims.tenant_id, claims.sub);
+        }
+
+        let tenant = req.extensions().get::<TenantContext>().map(|context| context.tenant_id.0);
+        let forwarded = self
+            .trust_forwarded_for
+            .then(|| {
+                req.headers()
+                    .get("x-forwarded-for")
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(|value| value.split(',').next())
+                    .and_then(|first| first.trim().parse::<std::net::IpAddr>().ok())
+            })
+            .flatten();
+        let address = forwarded.or_else(|| {
+            req.extensions()
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(addr)| addr.ip())
+        });
+        let address = address.map_or_else(|| "unknown".to_string(), |address| address.to_string());
+        match tenant {
+            Some(tenant) => format!("{}:ip:{}", tenant, address),
+            None => format!("ip:{}", address),
+        }
+    }
+
+    /// Standing of the caller after this call, and whether it went over the limit
+    async fn count_call(&self, caller: &str, bucket: &RouteBucket) -> Option<(RateLimitStanding, bool)> {
+        let rule = self.rule(bucket);
+        let key = format!("{}:{}:{}", RATE_LIMIT_PREFIX, caller, bucket.as_str());
+        match self.window.hit(&key, &rule, now_ms()).await {
+            Ok((calls, reset_ms)) => {
+                let exceeded = RateLimitStanding::exceeded(calls, rule.limit);
+                let outcome = if exceeded { "limited" } else { "allowed" };
+                self.requests.with_label_values(&[bucket.as_str(), outcome]).inc();
+                Some((RateLimitStanding::after(calls, rule.limit, reset_ms.div_ceil(1000).max(1)), exceeded))
+            }
+            Err(e) => {
+                warn!("Rate limit window unavailable, letting the call through: {}", e);
+                self.unavailable.inc();
+                None
+            }
+        }
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as u64)
+}
+
+/// Whether `standing` leaves fewer calls than the limit an inner layer reported
+fn tighter(response: &Response, standing: &RateLimitStanding) -> bool {
+    response
+        .headers()
+        .get("x-ratelimit-remaining")
+        .and_then(|value| value.to_str().ok()?.parse::<u32>().ok())
+        .is_none_or(|remaining| standing.remaining < remaining)
+}
+
+/// Counts the call in its route class's window; needs the [`RateLimiter`] as an
+/// extension, so it works with `axum::middleware::from_fn`
+pub async fn rate_limit_middleware(
+    Extension(limiter): Extension<Arc<RateLimiter>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let caller = limiter.caller(&req);
+    let bucket = limiter.bucket(req.uri().path());
+    let standing = limiter.count_call(&caller, &bucket).await;
+    let mut response = match standing {
+        Some((standing, true)) => rate_limited(&standing),
+        _ => next.run(req).await,
+    };
+    if let Some((standing, _)) = &standing {
+        if tighter(&response, standing) {
+            set_headers(&mut response, standing);
+        }
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::StatusCode, routing::get, Router};
+    use erp_core::config::JwtConfig;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+    use tower::ServiceExt;
+
+    /// Sliding windows in memory, on the time the test sets
+    #[derive(Default)]
+    struct MemoryWindow {
+        calls: Mutex<HashMap<String, Vec<u64>>>,
+        offset_ms: Mutex<u64>,
+    }
+
+    impl MemoryWindow {
+        fn advance(&self, by: Duration) {
+            *self.offset_ms.lock().unwrap() += by.as_millis() as u64;
+        }
+    }
+
+    #[async_trait]
+    impl SlidingWindow for MemoryWindow {
+        async fn hit(&self, key: &str, rule: &RateLimitRule, now_ms: u64) -> Result<(u64, u64)> {
+            let now_ms = now_ms + *self.offset_ms.lock().unwrap();
+            let window_ms = rule.window.as_millis() as u64;
+            let mut calls = self.calls.lock().unwrap();
+            let window = calls.entry(key.to_string()).or_default();
+            window.retain(|at| *at > now_ms.saturating_sub(window_ms));
+            window.push(now_ms);
+            let count = window.len() as u64;
+            if count > u64::from(rule.limit) {
+                window.pop();
+            }
+            Ok((count, (window[0] + window_ms).saturating_sub(now_ms)))
+        }
+    }
+
+    fn jwt() -> Arc<JwtService> {
+        Arc::new(
+            JwtService::new(&JwtConfig {
+                secret: "rate-limit-test-secret-of-at-least-32-chars".to_string(),
+                access_token_expiry: 900,
+                refresh_token_expiry: 3600,
+            })
+            .unwrap(),
+        )
+    }
+
+    fn limiter(window: Arc<MemoryWindow>, jwt: Arc<JwtService>) -> Arc<RateLimiter> {
+        let config = RateLimitConfig {
+            auth_login: "4/minute".to_string(),
+            api_default: "3/minute".to_string(),
+            routes: BTreeMap::from([("/auth/login".to_string(), "2/minute".to_string())]),
+            ..Default::default()
+        };
+        Arc::new(RateLimiter::new(window, jwt, &config, "test").unwrap())
+    }
+
+    async fn call(limiter: &Arc<RateLimiter>, path: &str, ip: &str, token: Option<&str>) -> Response {
+        call_for(limiter, path, ip, token, None).await
+    }
+
+    async fn call_for(
+        limiter: &Arc<RateLimiter>,
+        path: &str,
+        ip: &str,
+        token: Option<&str>,
+        tenant_id: Option<Uuid>,
+    ) -> Response {
+        let app = Router::new()
+            .route("/auth/login", get(|| async { "login" }))
+            .route("/auth/verify-2fa", get(|| async { "2fa" }))
+            .route("/products", get(|| async { "products" }))
+            .layer(axum::middleware::from_fn(rate_limit_middleware))
+            .layer(Extension(limiter.clone()));
+        let mut request = Request::builder().uri(path);
+        if let Some(token) = token {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+        let mut request = request.body(Body::empty()).unwrap();
+        request
+            .extensions_mut()
+            .insert(ConnectInfo(SocketAddr::new(ip.parse().unwrap(), 40000)));
+        if let Some(tenant_id) = tenant_id {
+            request.extensions_mut().insert(TenantContext {
+                tenant_id: erp_core::TenantId(tenant_id),
+                schema_name: "tenant_test".to_string(),
+            });
+        }
+        app.oneshot(request).await.unwrap()
+    }
+
+    #[test]
+    fn test_parse_rules() {
+        assert_eq!(
+            RateLimitRule::parse("10/minute"),
+            Ok(RateLimitRule { limit: 10, window: Duration::from_secs(60) })
+        );
+        assert_eq!(RateLimitRule::parse(" 5 / hour ").unwrap().window, Duration::from_secs(3600));
+        assert_eq!(RateLimitRule::parse("3/15minutes").unwrap().window, Duration::from_secs(900));
+        assert!(RateLimitRule::parse("10").is_err());
+        assert!(RateLimitRule::parse("0/minute").is_err());
+        assert!(RateLimitRule::parse("3/0minutes").is_err());
+        assert!(RateLimitRule::parse("3/15").is_err());
+        assert!(RateLimitRule::parse("ten/minute").is_err());
+        assert!(RateLimitRule::parse("10/week").is_err());
+    }
+
+    #[test]
+    fn test_auth_endpoints_have_their_own_bucket() {
+        let limiter = limiter(Arc::new(MemoryWindow::default()), jwt());
+        assert_eq!(limiter.bucket("/api/v1/auth/login"), RouteBucket::Route("/auth/login".to_string()));
+        assert_eq!(limiter.bucket("/auth/login/"), RouteBucket::Route("/auth/login".to_string()));
+        assert_eq!(limiter.bucket("/auth/forgot-password"), RouteBucket::AuthLogin);
+        assert_eq!(limiter.bucket("/authors"), RouteBucket::ApiDefault);
+        assert_eq!(limiter.bucket("/customers/search"), RouteBucket::ApiDefault);
+        assert_eq!(limiter.rule(&limiter.bucket("/auth/verify-2fa")).limit, 4);
+    }
+
+    #[test]
+    fn test_unparsable_route_limits_stop_the_start() {
+        let config = RateLimitConfig {
+            routes: BTreeMap::from([("/auth/login".to_string(), "often".to_string())]),
+            ..Default::default()
+        };
+        let error = RateLimiter::new(Arc::new(MemoryWindow::default()), jwt(), &config, "test").err().unwrap();
+        assert!(error.contains("/auth/login"));
+    }
+
+    #[tokio::test]
+    async fn test_login_bucket_is_stricter_and_answers_429() {
+        let window = Arc::new(MemoryWindow::default());
+        let limiter = limiter(window.clone(), jwt());
+
+        for remaining in ["1", "0"] {
+            let response = call(&limiter, "/auth/login", "10.0.0.1", None).await;
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(response.headers()["x-ratelimit-limit"], "2");
+            assert_eq!(response.headers()["x-ratelimit-remaining"], remaining);
+        }
+        let refused = call(&limiter, "/auth/login", "10.0.0.1", None).await;
+        assert_eq!(refused.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(refused.headers()[header::RETRY_AFTER], "60");
+        assert_eq!(refused.headers()["x-ratelimit-remaining"], "0");
+
+        // Other addresses, the other auth endpoints and the general bucket are not affected
+        assert_eq!(call(&limiter, "/auth/login", "10.0.0.2", None).await.status(), StatusCode::OK);
+        let verify = call(&limiter, "/auth/verify-2fa", "10.0.0.1", None).await;
+        assert_eq!(verify.status(), StatusCode::OK);
+        assert_eq!(verify.headers()["x-ratelimit-limit"], "4");
+        let products = call(&limiter, "/products", "10.0.0.1", None).await;
+        assert_eq!(products.status(), StatusCode::OK);
+        assert_eq!(products.headers()["x-ratelimit-limit"], "3");
+
+        let counted = |class: &str, outcome: &str| limiter.requests.with_label_values(&[class, outcome]).get();
+        assert_eq!(counted("/auth/login", "allowed"), 3);
+        assert_eq!(counted("/auth/login", "limited"), 1);
+        assert_eq!(counted("auth_login", "allowed"), 1);
+    }
+
+    #[tokio::test]
+    async fn test_requests_without_a_tenant_are_limited_by_address_alone() {
+        let window = Arc::new(MemoryWindow::default());
+        let limiter = limiter(window.clone(), jwt());
+        let (acme, globex) = (Uuid::new_v4(), Uuid::new_v4());
+
+        for _ in 0..2 {
+            call(&limiter, "/auth/login", "10.0.0.1", None).await;
+        }
+        assert_eq!(call(&limiter, "/auth/login", "10.0.0.1", None).await.status(), StatusCode::TOO_MANY_REQUESTS);
+
+        // Each tenant's login from the same address has a window of its own
+        for tenant in [acme, globex] {
+            for _ in 0..2 {
+                let response = call_for(&limiter, "/auth/login", "10.0.0.1", None, Some(tenant)).await;
+                assert_eq!(response.status(), StatusCode::OK);
+            }
+            let refused = call_for(&limiter, "/auth/login", "10.0.0.1", None, Some(tenant)).await;
+            assert_eq!(refused.status(), StatusCode::TOO_MANY_REQUESTS);
+        }
+
+        let keys: Vec<String> = window.calls.lock().unwrap().keys().cloned().collect();
+        assert!(keys.contains(&"rate_limit:window:ip:10.0.0.1:/auth/login".to_string()));
+        assert!(keys.contains(&format!("rate_limit:window:{}:ip:10.0.0.1:/auth/login", acme)));
+    }
+
+    #[tokio::test]
+    async fn test_window_slides_and_refused_calls_do_not_count() {
+        let window = Arc::new(MemoryWindow::default());
+        let limiter = limiter(window.clone(), jwt());
+
+        call(&limiter, "/auth/login", "10.0.0.1", None).await;
+        window.advance(Duration::from_secs(30));
+        call(&limiter, "/auth/login", "10.0.0.1", None).await;
+        for _ in 0..5 {
+            let refused = call(&limiter, "/auth/login", "10.0.0.1", None).await;
+            assert_eq!(refused.status(), StatusCode::TOO_MANY_REQUESTS);
+            assert_eq!(refused.headers()[header::RETRY_AFTER], "30");
+        }
+
+        // The first call has left the window, the refused ones never entered it
+        window.advance(Duration::from_secs(31));
+        assert_eq!(call(&limiter, "/auth/login", "10.0.0.1", None).await.status(), StatusCode::OK);
+        assert_eq!(
+            call(&limiter, "/auth/login", "10.0.0.1", None).await.status(),
+            StatusCode::TOO_MANY_REQUESTS
+        );
+    }
+
+    #[tokio::test]
+    async fn test_signed_in_calls_are_counted_per_user() {
+        let window = Arc::new(MemoryWindow::default());
+        let jwt = jwt();
+        let limiter = limiter(window, jwt.clone());
+        let token = |user: Uuid| {
+            jwt.generate_token_pair(&user.to_string(), &Uuid::new_v4().to_string(), vec![], vec![], None)
+                .unwrap()
+                .access_token
+        };
+        let (alice, bob) = (token(Uuid::new_v4()), token(Uuid::new_v4()));
+
+        // The same address, but different users
+        for _ in 0..3 {
+            assert_eq!(call(&limiter, "/products", "10.0.0.1", Some(&alice)).await.status(), StatusCode::OK);
+        }
+        let refused = call(&limiter, "/products", "10.0.0.1", Some(&alice)).await;
+        assert_eq!(refused.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(call(&limiter, "/products", "10.0.0.1", Some(&bob)).await.status(), StatusCode::OK);
+        assert_eq!(call(&limiter, "/products", "10.0.0.1", None).await.status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,69 @@
+//! # Sandbox Marker Middleware
+//!
+//! Marks responses to requests for a sandbox tenant with `X-Sandbox: true`, so
+//! integrators can tell test data from production at a glance. Runs inside
+//! the tenant context middleware and reads the [`TenantContext`] it inserted.
+
+use axum::{
+    extract::{Request, State},
+    http::HeaderValue,
+    middleware::Next,
+    response::Response,
+};
+use erp_core::{sandbox::SANDBOX_HEADER, TenantContext};
+use std::sync::Arc;
+
+use crate::sandbox::SandboxTenants;
+
+pub async fn sandbox_header_middleware(
+    State(sandboxes): State<Arc<SandboxTenants>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let sandbox = req
+        .extensions()
+        .get::<TenantContext>()
+        .is_some_and(|context| sandboxes.contains(context.tenant_id.0));
+
+    let mut response = next.run(req).await;
+    if sandbox {
+        response
+            .headers_mut()
+            .insert(SANDBOX_HEADER, HeaderValue::from_static("true"));
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, routing::get, Router};
+    use tower::ServiceExt;
+    use uuid::Uuid;
+
+    async fn call(sandboxes: Arc<SandboxTenants>, tenant_id: Uuid) -> Response {
+        let app = Router::new()
+            .route("/", get(|| async { "ok" }))
+            .layer(axum::middleware::from_fn_with_state(sandboxes, sandbox_header_middleware))
+            .layer(axum::Extension(TenantContext {
+                tenant_id: erp_core::TenantId(tenant_id),
+                schema_name: "tenant_test".to_string(),
+            }));
+        app.oneshot(Request::builder().uri("/").body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_only_sandbox_tenants_are_marked() {
+        let sandbox = Uuid::new_v4();
+        let sandboxes = Arc::new(SandboxTenants::default());
+        sandboxes.replace([sandbox]);
+
+        let response = call(sandboxes.clone(), sandbox).await;
+        assert_eq!(response.headers().get(SANDBOX_HEADER).unwrap(), "true");
+
+        let response = call(sandboxes, Uuid::new_v4()).await;
+        assert!(response.headers().get(SANDBOX_HEADER).is_none());
+    }
+}
@@ -5,21 +5,30 @@
 //! - X-Tenant-ID header (for API clients)
 //! - Subdomain extraction (for web applications)
 //! - JWT claims (for authenticated requests)
+//!
+//! The tenant's schema comes from the [`TenantContextResolver`]; a request for
+//! a tenant that does not exist is answered with 404.
+//!
+//! Handlers of tenant data extract a [`TenantUser`], which also checks that the
+//! caller's token was issued for the tenant the request names.
 
+use async_trait::async_trait;
 use axum::{
-    extract::{Host, Request},
-    http::{HeaderMap, StatusCode},
+    extract::{FromRequestParts, Host, Request, State},
+    http::{request::Parts, HeaderMap, StatusCode},
     middleware::Next,
     response::{IntoResponse, Response},
     Json,
 };
-use erp_core::TenantContext;
+use erp_core::{ErrorCode, RequestContext, TenantContext, TenantContextResolver};
 use serde_json::json;
+use std::sync::Arc;
 use tracing::{error, info, warn};
 use uuid::Uuid;
 
 /// Extract tenant context from the request
 pub async fn tenant_context_middleware(
+    State(tenants): State<Arc<TenantContextResolver>>,
     headers: HeaderMap,
     Host(host): Host,
     mut req: Request,
@@ -30,10 +39,22 @@ pub async fn tenant_context_middleware(
 
     match tenant_id {
         Some(tid) => {
-            // Create tenant context
-            let tenant_context = TenantContext {
-                tenant_id: erp_core::TenantId(tid),
-                schema_name: format!("tenant_{}", tid.to_string().replace('-', "_")),
+            let tenant_context = match tenants.resolve(tid).await {
+                Ok(tenant_context) => tenant_context,
+                Err(e) if e.code == ErrorCode::ResourceNotFound => {
+                    warn!(tenant_id = %tid, "Request for unknown tenant");
+                    return (
+                        StatusCode::NOT_FOUND,
+                        Json(json!({
+                            "error": "Unknown tenant",
+                            "message": format!("Tenant {} does not exist", tid)
+                        }))
+                    ).into_response();
+                }
+                Err(e) => {
+                    error!(tenant_id = %tid, error = %e, "Failed to resolve tenant context");
+                    return StatusCode::SERVICE_UNAVAILABLE.into_response();
+                }
             };
 
             info!(
@@ -170,4 +191,96 @@ pub async fn require_tenant_context(
 /// Extract tenant context from request extensions
 pub fn extract_tenant_context(req: &Request) -> Option<TenantContext> {
     req.extensions().get::<TenantContext>().cloned()
-}
\ No newline at end of file
+}
+/// The signed-in user of a request, acting in the tenant the request names
+///
+/// Extracting it refuses requests without a tenant context (400), without a
+/// signed-in user (401) and with a token issued for another tenant (403).
+#[derive(Debug, Clone)]
+pub struct TenantUser {
+    pub tenant_context: TenantContext,
+    pub request_context: RequestContext,
+    pub user_id: Uuid,
+}
+
+impl TenantUser {
+    /// Refuses customer portal accounts on endpoints meant for staff
+    pub fn staff_only(self) -> Result<Self, StatusCode> {
+        match self.request_context.portal {
+            Some(_) => Err(StatusCode::FORBIDDEN),
+            None => Ok(self),
+        }
+    }
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for TenantUser
+where
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Some(tenant_context) = parts.extensions.get::<TenantContext>().cloned() else {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(json!({ "success": false, "error": "Missing tenant context" })),
+            )
+                .into_response());
+        };
+        let request_context = RequestContext::from_request_parts(parts, state)
+            .await
+            .map_err(IntoResponse::into_response)?;
+
+        let token_tenant = request_context.tenant_context.as_ref().map(|t| t.tenant_id);
+        match request_context.user_id {
+            Some(user_id) if token_tenant == Some(tenant_context.tenant_id) => Ok(Self {
+                tenant_context,
+                request_context,
+                user_id,
+            }),
+            _ => {
+                warn!(tenant_id = %tenant_context.tenant_id.0, "Token was not issued for the requested tenant");
+                Err((StatusCode::FORBIDDEN, Json(json!({ "success": false, "error": "Forbidden" }))).into_response())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use erp_core::TenantId;
+
+    fn tenant(tenant_id: Uuid) -> TenantContext {
+        TenantContext { tenant_id: TenantId(tenant_id), schema_name: "tenant_test".to_string() }
+    }
+
+    async fn extract(requested: Uuid, token: Uuid) -> Result<TenantUser, StatusCode> {
+        let mut request_context = RequestContext::new();
+        request_context.tenant_context = Some(tenant(token));
+        request_context.user_id = Some(Uuid::new_v4());
+
+        let request = Request::builder()
+            .extension(tenant(requested))
+            .extension(request_context)
+            .body(())
+            .unwrap();
+        let (mut parts, _) = request.into_parts();
+        TenantUser::from_request_parts(&mut parts, &()).await.map_err(|response| response.status())
+    }
+
+    #[tokio::test]
+    async fn test_token_of_the_requested_tenant_is_accepted() {
+        let tenant_id = Uuid::new_v4();
+        let user = extract(tenant_id, tenant_id).await.unwrap();
+        assert_eq!(user.tenant_context.tenant_id.0, tenant_id);
+        assert_eq!(Some(user.user_id), user.request_context.user_id);
+    }
+
+    #[tokio::test]
+    async fn test_token_of_another_tenant_is_forbidden() {
+        let status = extract(Uuid::new_v4(), Uuid::new_v4()).await.unwrap_err();
+        assert_eq!(status, StatusCode::FORBIDDEN);
+    }
+}
@@ -0,0 +1,145 @@
+//! # Response Warnings Middleware
+//!
+//! Collects the warnings raised while a request is handled (see
+//! [`erp_core::warnings`]) and returns them with the response. A JSON object
+//! body gets them as a `warnings` array; any other response gets one
+//! `X-Warning: CODE: message` header per warning. Responses without warnings
+//! are passed through untouched, so they never carry the key. The status and
+//! the other headers are kept as they are.
+
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{header, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use erp_core::warnings::{self, Warning, WARNING_HEADER};
+use serde_json::Value;
+
+pub async fn warnings_middleware(req: Request, next: Next) -> Response {
+    let (response, warnings) = warnings::collect(next.run(req)).await;
+    if warnings.is_empty() {
+        return response;
+    }
+    attach_warnings(response, warnings).await
+}
+
+async fn attach_warnings(response: Response, warnings: Vec<Warning>) -> Response {
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/json"));
+    if !is_json {
+        return with_warning_headers(response, &warnings);
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            tracing::error!("Failed to read response body to attach warnings: {}", e);
+            return with_warning_headers(Response::from_parts(parts, Body::empty()), &warnings);
+        }
+    };
+    match serde_json::from_slice::<Value>(&bytes) {
+        Ok(Value::Object(mut object)) => {
+            object.insert("warnings".to_string(), serde_json::to_value(&warnings).unwrap_or_default());
+            parts.headers.remove(header::CONTENT_LENGTH);
+            Response::from_parts(parts, Body::from(Value::Object(object).to_string()))
+        }
+        _ => with_warning_headers(Response::from_parts(parts, Body::from(bytes)), &warnings),
+    }
+}
+
+fn with_warning_headers(mut response: Response, warnings: &[Warning]) -> Response {
+    for warning in warnings {
+        let value = HeaderValue::from_str(&format!("{}: {}", warning.code.as_str(), warning.message))
+            .unwrap_or_else(|_| HeaderValue::from_static(warning.code.as_str()));
+        response.headers_mut().append(WARNING_HEADER, value);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        http::StatusCode,
+        response::{IntoResponse, Json},
+        routing::{get, post},
+        Router,
+    };
+    use erp_core::WarningCode;
+    use erp_master_data::customer::validation::warn_unverifiable_tax_numbers;
+    use serde_json::json;
+    use std::collections::HashMap;
+    use tower::ServiceExt;
+
+    fn app() -> Router {
+        Router::new()
+            .route(
+                "/customers",
+                post(|| async {
+                    // Service layer: the tax number check of customer create
+                    warn_unverifiable_tax_numbers(&HashMap::from([("vat".to_string(), "12 34".to_string())]));
+                    // Handler layer
+                    warnings::warn(Warning::new(WarningCode::MaskedFieldsOmitted, "Bank accounts are not returned"));
+                    (StatusCode::CREATED, Json(json!({ "success": true, "id": 1 })))
+                }),
+            )
+            .route("/plain", get(|| async { Json(json!({ "success": true })) }))
+            .route(
+                "/export",
+                get(|| async {
+                    warnings::warn(Warning::new(WarningCode::MaskedFieldsOmitted, "Masked values left out"));
+                    "a,b\n".into_response()
+                }),
+            )
+            .layer(axum::middleware::from_fn(warnings_middleware))
+    }
+
+    async fn call(method: &str, uri: &str) -> (StatusCode, axum::http::HeaderMap, Vec<u8>) {
+        let response = app()
+            .oneshot(Request::builder().method(method).uri(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let (parts, body) = response.into_parts();
+        (parts.status, parts.headers, axum::body::to_bytes(body, usize::MAX).await.unwrap().to_vec())
+    }
+
+    #[tokio::test]
+    async fn test_warnings_of_every_layer_reach_the_response() {
+        let (status, _, body) = call("POST", "/customers").await;
+        assert_eq!(status, StatusCode::CREATED);
+
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["success"], true);
+        assert_eq!(body["id"], 1);
+        let warnings = body["warnings"].as_array().unwrap();
+        assert_eq!(warnings.len(), 2);
+        assert_eq!(warnings[0]["code"], "TAX_ID_UNVERIFIED");
+        assert_eq!(warnings[0]["field"], "tax_numbers.vat");
+        assert_eq!(warnings[1]["code"], "MASKED_FIELDS_OMITTED");
+        assert!(warnings[1].get("field").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_responses_without_warnings_have_no_array() {
+        for _ in 0..2 {
+            let (status, headers, body) = call("GET", "/plain").await;
+            assert_eq!(status, StatusCode::OK);
+            assert!(headers.get(WARNING_HEADER).is_none());
+            assert_eq!(serde_json::from_slice::<Value>(&body).unwrap(), json!({ "success": true }));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_non_json_responses_carry_warning_headers() {
+        let (status, headers, body) = call("GET", "/export").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, b"a,b\n");
+        assert_eq!(headers.get(WARNING_HEADER).unwrap(), "MASKED_FIELDS_OMITTED: Masked values left out");
+    }
+}
@@ -0,0 +1,367 @@
+//! # API Usage Metering
+//!
+//! [`api_usage_middleware`] runs around every `/api/v1` route. For calls with
+//! a valid access token it counts the call against the token's rate limit,
+//! answers 429 once the limit is used up, and reports the call with its
+//! status, latency and bytes to the [`ApiUsageRecorder`]. The write loop sums
+//! the reported calls and adds them to `public.api_usage_daily` every
+//! `api_usage.flush_interval_secs`; the admin endpoints under
+//! `/api/v1/usage/api` report from that table.
+//!
+//! The limit is `rate_limit.requests_per_minute` plus `rate_limit.burst_size`
+//! calls per token and fixed minute, counted in Redis under
+//! `rate_limit:token:{jti}`. If Redis cannot be reached the call is let
+//! through without rate-limit headers rather than refused.
+
+use async_trait::async_trait;
+use axum::{
+    body::HttpBody,
+    extract::{Request, State},
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::Utc;
+use erp_core::api_usage::{endpoint_group, ApiCall, ApiUsageRecorder, ApiUsageStore, RateLimitStanding, UsageTally};
+use erp_core::security::JwtService;
+use erp_core::{ApiUsageConfig, Result};
+use prometheus::IntCounter;
+use redis::aio::ConnectionManager;
+use serde_json::json;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::mpsc;
+use tracing::warn;
+use uuid::Uuid;
+
+/// Length of a rate-limit window
+const WINDOW_SECS: u64 = 60;
+
+/// Calls of a token, or another caller, in the current rate-limit window
+#[async_trait]
+pub trait RateLimitCounter: Send + Sync {
+    /// Count a call; the calls so far in the window and seconds until it ends
+    async fn hit(&self, caller: &str) -> Result<(u64, u64)>;
+}
+
+/// Per-caller counters in Redis that expire with their window
+pub struct RedisRateLimitCounter {
+    redis: ConnectionManager,
+    prefix: &'static str,
+}
+
+impl RedisRateLimitCounter {
+    /// Counters of access tokens, `rate_limit:token:{jti}`
+    pub fn new(redis: ConnectionManager) -> Self {
+        Self::with_prefix(redis, "rate_limit:token")
+    }
+
+    /// Counters under `{prefix}:{caller}`
+    pub fn with_prefix(redis: ConnectionManager, prefix: &'static str) -> Self {
+        Self { redis, prefix }
+    }
+}
+
+#[async_trait]
+impl RateLimitCounter for RedisRateLimitCounter {
+    async fn hit(&self, caller: &str) -> Result<(u64, u64)> {
+        let key = format!("{}:{}", self.prefix, caller);
+        let mut redis = self.redis.clone();
+        // The first call of a window creates the key with its expiry
+        let (count, ttl): (u64, i64) = redis::pipe()
+            .atomic()
+            .cmd("SET").arg(&key).arg(0).arg("EX").arg(WINDOW_SECS).arg("NX").ignore()
+            .cmd("INCR").arg(&key)
+            .cmd("TTL").arg(&key)
+            .query_async(&mut redis)
+            .await?;
+        Ok((count, ttl.max(0) as u64))
+    }
+}
+
+/// Who made a call, read from its bearer token
+struct Caller {
+    tenant_id: Uuid,
+    user_id: Uuid,
+    token_id: String,
+}
+
+pub struct ApiUsageService {
+    store: ApiUsageStore,
+    recorder: ApiUsageRecorder,
+    jwt: Arc<JwtService>,
+    limiter: Option<Arc<dyn RateLimitCounter>>,
+    limit: u32,
+    config: ApiUsageConfig,
+}
+
+impl ApiUsageService {
+    /// The service and the channel `spawn` drains; `limit` is the number of
+    /// calls a token may make per minute
+    pub fn new(
+        store: ApiUsageStore,
+        jwt: Arc<JwtService>,
+        limiter: Arc<dyn RateLimitCounter>,
+        limit: u32,
+        config: ApiUsageConfig,
+        namespace: &str,
+    ) -> std::result::Result<(Self, mpsc::Receiver<ApiCall>), String> {
+        let (recorder, calls) = ApiUsageRecorder::new(config.channel_capacity, namespace)?;
+        let limiter = config.rate_limiting.then_some(limiter);
+        Ok((Self { store, recorder, jwt, limiter, limit, config }, calls))
+    }
+
+    pub fn store(&self) -> &ApiUsageStore {
+        &self.store
+    }
+
+    pub fn config(&self) -> &ApiUsageConfig {
+        &self.config
+    }
+
+    /// Counter of calls dropped because the channel was full
+    pub fn dropped_counter(&self) -> IntCounter {
+        self.recorder.dropped_counter()
+    }
+
+    /// Whether the middleware has anything to do
+    pub fn active(&self) -> bool {
+        self.config.enabled || self.limiter.is_some()
+    }
+
+    fn caller(&self, req: &Request) -> Option<Caller> {
+        let token = req
+            .headers()
+            .get(header::AUTHORIZATION)?
+            .to_str()
+            .ok()?
+            .strip_prefix("Bearer ")?;
+        let claims = self.jwt.verify_access_token(token).ok()?;
+        Some(Caller {
+            tenant_id: claims.tenant_id.parse().ok()?,
+            user_id: claims.sub.parse().ok()?,
+            token_id: claims.jti,
+        })
+    }
+
+    /// Standing of the token after this call, and whether it went over the limit
+    async fn count_call(&self, token_id: &str) -> Option<(RateLimitStanding, bool)> {
+        let limiter = self.limiter.as_ref()?;
+        match limiter.hit(token_id).await {
+            Ok((calls, reset_secs)) => Some((
+                RateLimitStanding::after(calls, self.limit, reset_secs),
+                RateLimitStanding::exceeded(calls, self.limit),
+            )),
+            Err(e) => {
+                warn!("Rate limit counter unavailable, letting the call through: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Start the loop that stores the recorded calls
+    pub fn spawn(self: &Arc<Self>, calls: mpsc::Receiver<ApiCall>) {
+        if !self.config.enabled {
+            return;
+        }
+        tokio::spawn(Arc::clone(self).write_loop(calls));
+    }
+
+    async fn write_loop(self: Arc<Self>, mut calls: mpsc::Receiver<ApiCall>) {
+        let mut ticker = tokio::time::interval(std::time::Duration::from_secs(self.config.flush_interval_secs.max(1)));
+        let mut tally = UsageTally::default();
+
+        loop {
+            tokio::select! {
+                call = calls.recv() => match call {
+                    Some(call) => tally.add(call),
+                    None => {
+                        self.flush(&mut tally).await;
+                        return;
+                    }
+                },
+                _ = ticker.tick() => self.flush(&mut tally).await,
+            }
+        }
+    }
+
+    /// Store the summed calls; kept for the next tick if the database fails
+    async fn flush(&self, tally: &mut UsageTally) {
+        if tally.is_empty() {
+            return;
+        }
+        let counts = tally.take();
+        if let Err(e) = self.store.add(&counts).await {
+            warn!("Failed to store {} API usage counts: {}", counts.len(), e);
+            for count in counts {
+                tally.restore(count);
+            }
+        }
+    }
+}
+
+pub(crate) fn set_headers(response: &mut Response, standing: &RateLimitStanding) {
+    for (name, value) in standing.headers() {
+        if let Ok(value) = HeaderValue::from_str(&value) {
+            response.headers_mut().insert(name, value);
+        }
+    }
+}
+
+/// 429 with `Retry-After` at the end of the window
+pub(crate) fn rate_limited(standing: &RateLimitStanding) -> Response {
+    let mut response = (
+        StatusCode::TOO_MANY_REQUESTS,
+        Json(json!({ "success": false, "error": "Rate limit exceeded" })),
+    )
+        .into_response();
+    response
+        .headers_mut()
+        .insert(header::RETRY_AFTER, HeaderValue::from(standing.reset_secs));
+    response
+}
+
+pub async fn api_usage_middleware(State(usage): State<Arc<ApiUsageService>>, req: Request, next: Next) -> Response {
+    let Some(caller) = usage.active().then(|| usage.caller(&req)).flatten() else {
+        return next.run(req).await;
+    };
+    let started = Instant::now();
+    let group = endpoint_group(req.uri().path());
+    let request_bytes = req
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|length| length.to_str().ok()?.parse().ok())
+        .unwrap_or(0);
+
+    let standing = usage.count_call(&caller.token_id).await;
+    let mut response = match standing {
+        Some((standing, true)) => rate_limited(&standing),
+        _ => next.run(req).await,
+    };
+    if let Some((standing, _)) = &standing {
+        set_headers(&mut response, standing);
+    }
+
+    if usage.config.enabled {
+        let response_bytes = response.body().size_hint().exact().unwrap_or(0);
+        usage.recorder.record(ApiCall {
+            tenant_id: caller.tenant_id,
+            user_id: caller.user_id,
+            token_id: caller.token_id,
+            endpoint_group: group,
+            status: response.status().as_u16(),
+            latency_ms: started.elapsed().as_millis() as u64,
+            bytes: request_bytes + response_bytes,
+            date: Utc::now().date_naive(),
+        });
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, routing::get, Router};
+    use erp_core::config::JwtConfig;
+    use sqlx::postgres::PgPoolOptions;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+    use tower::ServiceExt;
+
+    /// Counts calls in memory; the window never ends
+    #[derive(Default)]
+    struct MemoryCounter {
+        calls: Mutex<HashMap<String, u64>>,
+    }
+
+    #[async_trait]
+    impl RateLimitCounter for MemoryCounter {
+        async fn hit(&self, token_id: &str) -> Result<(u64, u64)> {
+            let mut calls = self.calls.lock().unwrap();
+            let count = calls.entry(token_id.to_string()).or_default();
+            *count += 1;
+            Ok((*count, 42))
+        }
+    }
+
+    fn service(rate_limiting: bool) -> (Arc<ApiUsageService>, mpsc::Receiver<ApiCall>, String) {
+        let jwt = Arc::new(
+            JwtService::new(&JwtConfig {
+                secret: "api-usage-test-secret-of-at-least-32-chars".to_string(),
+                access_token_expiry: 900,
+                refresh_token_expiry: 3600,
+            })
+            .unwrap(),
+        );
+        let token = jwt
+            .generate_token_pair(&Uuid::new_v4().to_string(), &Uuid::new_v4().to_string(), vec![], vec![], None)
+            .unwrap()
+            .access_token;
+        // Never connected: the middleware does not touch the store
+        let pool = PgPoolOptions::new().connect_lazy("postgres://localhost/unused").unwrap();
+        let config = ApiUsageConfig { rate_limiting, ..Default::default() };
+        let (service, calls) = ApiUsageService::new(
+            ApiUsageStore::new(pool),
+            jwt,
+            Arc::new(MemoryCounter::default()),
+            2,
+            config,
+            "test",
+        )
+        .unwrap();
+        (Arc::new(service), calls, token)
+    }
+
+    async fn call(service: &Arc<ApiUsageService>, token: Option<&str>) -> Response {
+        let app = Router::new()
+            .route("/products", get(|| async { "products" }))
+            .layer(axum::middleware::from_fn_with_state(service.clone(), api_usage_middleware));
+        let mut request = Request::builder().uri("/products");
+        if let Some(token) = token {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+        app.oneshot(request.body(Body::empty()).unwrap()).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_headers_and_429() {
+        let (service, mut calls, token) = service(true);
+
+        let response = call(&service, Some(&token)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["x-ratelimit-limit"], "2");
+        assert_eq!(response.headers()["x-ratelimit-remaining"], "1");
+        assert_eq!(response.headers()["x-ratelimit-reset"], "42");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&body[..], b"products");
+
+        assert_eq!(call(&service, Some(&token)).await.headers()["x-ratelimit-remaining"], "0");
+        let limited = call(&service, Some(&token)).await;
+        assert_eq!(limited.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(limited.headers()[header::RETRY_AFTER], "42");
+        assert_eq!(limited.headers()["x-ratelimit-remaining"], "0");
+
+        // Every call is metered, the refused one as rate limited
+        let metered: Vec<ApiCall> = (0..3).map(|_| calls.try_recv().unwrap()).collect();
+        assert_eq!(metered.iter().map(|c| c.status).collect::<Vec<_>>(), vec![200, 200, 429]);
+        assert!(metered.iter().all(|c| c.endpoint_group == "products"));
+        assert_eq!(metered[0].bytes, 8, "the response body");
+
+        // Calls without a valid token are neither limited nor metered
+        let anonymous = call(&service, None).await;
+        assert!(anonymous.headers().get("x-ratelimit-limit").is_none());
+        assert!(calls.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_no_headers_without_rate_limiting() {
+        let (service, mut calls, token) = service(false);
+        for _ in 0..3 {
+            let response = call(&service, Some(&token)).await;
+            assert_eq!(response.status(), StatusCode::OK);
+            assert!(response.headers().get("x-ratelimit-limit").is_none());
+        }
+        assert_eq!(calls.try_recv().unwrap().status, 200);
+    }
+}
@@ -0,0 +1,653 @@
+//! # Backpressure for Expensive Endpoints
+//!
+//! Reports and exports are computed synchronously and can load the database
+//! heavily; when many arrive at once, running them all makes every one of
+//! them slower. Such endpoints are put in a group (see [`REPORTS_GROUP`] and
+//! [`EXPORTS_GROUP`]) whose requests run at most
+//! `backpressure.groups.<name>` at a time; the others wait for a free slot.
+//!
+//! - A request that waited `backpressure.queue_timeout_ms` is shed with
+//!   `503 Service Unavailable` and `Retry-After`.
+//! - A request sent with `Prefer: respond-async` (RFC 7240) is answered right
+//!   away with `202 Accepted` and a `Location` under
+//!   [`ASYNC_RESULTS_PATH`]. It waits for a slot without a timeout, and its
+//!   response is kept in `public.async_results` for
+//!   `backpressure.async_result_ttl_secs`, readable only by the same user.
+//!
+//! Waiting requests, the time they waited and the shed ones are exported per
+//! group as metrics.
+
+use async_trait::async_trait;
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use erp_core::{BackpressureConfig, MetricsRegistry, RequestContext, Result, TenantContext};
+use prometheus::{HistogramOpts, HistogramVec, IntCounterVec, IntGaugeVec, Opts};
+use serde::Serialize;
+use serde_json::json;
+use sqlx::{PgPool, Row};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// Valuation, KPI, profitability and dashboard reports
+pub const REPORTS_GROUP: &str = "reports";
+
+/// Exports of whole feeds and usage tables
+pub const EXPORTS_GROUP: &str = "exports";
+
+/// Where results of asynchronously answered requests are fetched
+pub const ASYNC_RESULTS_PATH: &str = "/api/v1/async-results";
+
+/// `Prefer` token asking for a 202 instead of waiting for the response
+const RESPOND_ASYNC: &str = "respond-async";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AsyncResultStatus {
+    Pending,
+    Completed,
+    Failed,
+}
+
+impl AsyncResultStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AsyncResultStatus::Pending => "pending",
+            AsyncResultStatus::Completed => "completed",
+            AsyncResultStatus::Failed => "failed",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "pending" => Some(AsyncResultStatus::Pending),
+            "completed" => Some(AsyncResultStatus::Completed),
+            "failed" => Some(AsyncResultStatus::Failed),
+            _ => None,
+        }
+    }
+}
+
+/// A request answered with 202 and, once it ran, its response
+#[derive(Debug, Clone, Serialize)]
+pub struct AsyncResult {
+    pub id: Uuid,
+    pub tenant_id: Uuid,
+    pub user_id: Option<Uuid>,
+    pub endpoint_group: String,
+    pub method: String,
+    pub path: String,
+    pub status: AsyncResultStatus,
+    pub status_code: Option<u16>,
+    pub content_type: Option<String>,
+    #[serde(skip)]
+    pub body: Option<Vec<u8>>,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Storage of asynchronously answered requests
+#[async_trait]
+pub trait AsyncResultStore: Send + Sync {
+    async fn create(&self, result: &AsyncResult) -> Result<()>;
+
+    async fn complete(&self, id: Uuid, status_code: u16, content_type: Option<&str>, body: &[u8]) -> Result<()>;
+
+    async fn fail(&self, id: Uuid, error: &str) -> Result<()>;
+
+    /// The result if it belongs to the tenant and has not expired at `now`
+    async fn get(&self, tenant_id: Uuid, id: Uuid, now: DateTime<Utc>) -> Result<Option<AsyncResult>>;
+
+    async fn delete_expired(&self, now: DateTime<Utc>) -> Result<u64>;
+}
+
+pub struct PostgresAsyncResultStore {
+    pool: PgPool,
+}
+
+impl PostgresAsyncResultStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl AsyncResultStore for PostgresAsyncResultStore {
+    async fn create(&self, result: &AsyncResult) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO public.async_results \
+             (id, tenant_id, user_id, endpoint_group, method, path, status, created_at, expires_at) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)",
+        )
+        .bind(result.id)
+        .bind(result.tenant_id)
+        .bind(result.user_id)
+        .bind(&result.endpoint_group)
+        .bind(&result.method)
+        .bind(&result.path)
+        .bind(result.status.as_str())
+        .bind(result.created_at)
+        .bind(result.expires_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn complete(&self, id: Uuid, status_code: u16, content_type: Option<&str>, body: &[u8]) -> Result<()> {
+        sqlx::query(
+            "UPDATE public.async_results \
+             SET status = 'completed', status_code = $2, content_type = $3, body = $4, completed_at = NOW() \
+             WHERE id = $1",
+        )
+        .bind(id)
+        .bind(status_code as i32)
+        .bind(content_type)
+        .bind(body)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn fail(&self, id: Uuid, error: &str) -> Result<()> {
+        sqlx::query(
+            "UPDATE public.async_results SET status = 'failed', error = $2, completed_at = NOW() WHERE id = $1",
+        )
+        .bind(id)
+        .bind(error)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn get(&self, tenant_id: Uuid, id: Uuid, now: DateTime<Utc>) -> Result<Option<AsyncResult>> {
+        let row = sqlx::query(
+            "SELECT id, tenant_id, user_id, endpoint_group, method, path, status, status_code, content_type, body, \
+                    error, created_at, completed_at, expires_at \
+             FROM public.async_results WHERE tenant_id = $1 AND id = $2 AND expires_at > $3",
+        )
+        .bind(tenant_id)
+        .bind(id)
+        .bind(now)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        let status: String = row.try_get("status")?;
+        let status_code: Option<i32> = row.try_get("status_code")?;
+        Ok(Some(AsyncResult {
+            id: row.try_get("id")?,
+            tenant_id: row.try_get("tenant_id")?,
+            user_id: row.try_get("user_id")?,
+            endpoint_group: row.try_get("endpoint_group")?,
+            method: row.try_get("method")?,
+            path: row.try_get("path")?,
+            status: AsyncResultStatus::parse(&status).unwrap_or(AsyncResultStatus::Failed),
+            status_code: status_code.map(|code| code as u16),
+            content_type: row.try_get("content_type")?,
+            body: row.try_get("body")?,
+            error: row.try_get("error")?,
+            created_at: row.try_get("created_at")?,
+            completed_at: row.try_get("completed_at")?,
+            expires_at: row.try_get("expires_at")?,
+        }))
+    }
+
+    async fn delete_expired(&self, now: DateTime<Utc>) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM public.async_results WHERE expires_at <= $1")
+            .bind(now)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+}
+
+/// Concurrency limits of all endpoint groups and the results of requests answered asynchronously
+pub struct Backpressure {
+    config: BackpressureConfig,
+    results: Arc<dyn AsyncResultStore>,
+    semaphores: Mutex<HashMap<String, Arc<Semaphore>>>,
+    queue_depth: IntGaugeVec,
+    wait_seconds: HistogramVec,
+    shed: IntCounterVec,
+}
+
+impl Backpressure {
+    pub fn new(
+        config: BackpressureConfig,
+        results: Arc<dyn AsyncResultStore>,
+        namespace: &str,
+    ) -> std::result::Result<Self, prometheus::Error> {
+        Ok(Self {
+            config,
+            results,
+            semaphores: Mutex::new(HashMap::new()),
+            queue_depth: IntGaugeVec::new(
+                Opts::new(
+                    format!("{}_backpressure_queue_depth", namespace),
+                    "Requests waiting for a free slot of their endpoint group",
+                ),
+                &["group"],
+            )?,
+            wait_seconds: HistogramVec::new(
+                HistogramOpts::new(
+                    format!("{}_backpressure_wait_seconds", namespace),
+                    "Time requests waited for a free slot of their endpoint group",
+                )
+                .buckets(vec![0.005, 0.025, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0]),
+                &["group"],
+            )?,
+            shed: IntCounterVec::new(
+                Opts::new(
+                    format!("{}_backpressure_shed_total", namespace),
+                    "Requests answered with 503 after waiting too long for their endpoint group",
+                ),
+                &["group"],
+            )?,
+        })
+    }
+
+    pub fn register(&self, metrics: &MetricsRegistry) -> std::result::Result<(), prometheus::Error> {
+        metrics.register(self.queue_depth.clone())?;
+        metrics.register(self.wait_seconds.clone())?;
+        metrics.register(self.shed.clone())?;
+        Ok(())
+    }
+
+    pub fn results(&self) -> Arc<dyn AsyncResultStore> {
+        self.results.clone()
+    }
+
+    /// Middleware state limiting the endpoints of `name`; groups of the same name share their slots
+    pub fn group(self: &Arc<Self>, name: &str) -> EndpointGroup {
+        let semaphore = self
+            .semaphores
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(name.to_string())
+            .or_insert_with(|| Arc::new(Semaphore::new(self.config.max_concurrent(name))))
+            .clone();
+        EndpointGroup {
+            name: name.to_string(),
+            semaphore,
+            backpressure: self.clone(),
+        }
+    }
+
+    /// Delete expired results every few minutes; `None` when disabled
+    pub fn spawn(self: &Arc<Self>) -> Option<tokio::task::JoinHandle<()>> {
+        if !self.config.enabled {
+            return None;
+        }
+
+        let backpressure = Arc::clone(self);
+        let every = Duration::from_secs(self.config.async_result_ttl_secs.clamp(60, 900));
+        Some(tokio::spawn(async move {
+            let mut interval = tokio::time::interval(every);
+            loop {
+                interval.tick().await;
+                match backpressure.results.delete_expired(Utc::now()).await {
+                    Ok(0) => {}
+                    Ok(deleted) => info!("Deleted {} expired async results", deleted),
+                    Err(e) => warn!("Deleting expired async results failed: {}", e),
+                }
+            }
+        }))
+    }
+}
+
+/// One endpoint group, the state of [`backpressure_middleware`]
+#[derive(Clone)]
+pub struct EndpointGroup {
+    name: String,
+    semaphore: Arc<Semaphore>,
+    backpressure: Arc<Backpressure>,
+}
+
+impl EndpointGroup {
+    /// A slot of the group; `None` if none got free within `timeout`
+    async fn acquire(&self, timeout: Option<Duration>) -> Option<OwnedSemaphorePermit> {
+        if let Ok(permit) = self.semaphore.clone().try_acquire_owned() {
+            self.backpressure.wait_seconds.with_label_values(&[&self.name]).observe(0.0);
+            return Some(permit);
+        }
+
+        let started = Instant::now();
+        let depth = self.backpressure.queue_depth.with_label_values(&[&self.name]);
+        depth.inc();
+        let permit = match timeout {
+            Some(timeout) => tokio::time::timeout(timeout, self.semaphore.clone().acquire_owned()).await.ok(),
+            None => Some(self.semaphore.clone().acquire_owned().await),
+        };
+        depth.dec();
+
+        // The semaphores are never closed
+        let permit = permit.and_then(|permit| permit.ok());
+        if permit.is_some() {
+            self.backpressure
+                .wait_seconds
+                .with_label_values(&[&self.name])
+                .observe(started.elapsed().as_secs_f64());
+        }
+        permit
+    }
+
+    fn shed(&self) -> Response {
+        self.backpressure.shed.with_label_values(&[&self.name]).inc();
+        let retry_after = self.backpressure.config.retry_after_secs;
+        let mut response = (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(json!({
+                "success": false,
+                "error": format!("Too many {} requests are running; try again later", self.name),
+                "retry_after_seconds": retry_after,
+                "hint": "Send `Prefer: respond-async` to have the request queued and fetch its result later"
+            })),
+        )
+            .into_response();
+        response.headers_mut().insert(header::RETRY_AFTER, HeaderValue::from(retry_after));
+        response
+    }
+
+    /// Answer with 202 and run the request once a slot is free
+    async fn respond_async(&self, req: Request, next: Next, tenant_id: Uuid) -> Response {
+        let config = &self.backpressure.config;
+        let now = Utc::now();
+        let result = AsyncResult {
+            id: Uuid::new_v4(),
+            tenant_id,
+            user_id: req.extensions().get::<RequestContext>().and_then(|ctx| ctx.user_id),
+            endpoint_group: self.name.clone(),
+            method: req.method().to_string(),
+            path: req.uri().path_and_query().map(|p| p.to_string()).unwrap_or_default(),
+            status: AsyncResultStatus::Pending,
+            status_code: None,
+            content_type: None,
+            body: None,
+            error: None,
+            created_at: now,
+            completed_at: None,
+            expires_at: now + ChronoDuration::seconds(config.async_result_ttl_secs as i64),
+        };
+        if let Err(e) = self.backpressure.results.create(&result).await {
+            warn!("Storing async result for {} failed: {}", result.path, e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+
+        let group = self.clone();
+        let id = result.id;
+        tokio::spawn(async move {
+            let permit = group.acquire(None).await;
+            let (parts, body) = next.run(req).await.into_parts();
+            let max_bytes = group.backpressure.config.max_async_result_bytes;
+            let stored = match axum::body::to_bytes(body, max_bytes).await {
+                Ok(body) => {
+                    let content_type = parts.headers.get(header::CONTENT_TYPE).and_then(|v| v.to_str().ok());
+                    group.backpressure.results.complete(id, parts.status.as_u16(), content_type, &body).await
+                }
+                Err(_) => {
+                    let error = format!("The response is larger than {} bytes; request it synchronously", max_bytes);
+                    group.backpressure.results.fail(id, &error).await
+                }
+            };
+            drop(permit);
+            if let Err(e) = stored {
+                warn!("Storing async result {} failed: {}", id, e);
+            }
+        });
+
+        let location = format!("{}/{}", ASYNC_RESULTS_PATH, id);
+        let mut response = (
+            StatusCode::ACCEPTED,
+            Json(json!({
+                "success": true,
+                "id": id,
+                "status": result.status,
+                "status_url": location,
+                "expires_at": result.expires_at
+            })),
+        )
+            .into_response();
+        let headers = response.headers_mut();
+        if let Ok(location) = HeaderValue::from_str(&location) {
+            headers.insert(header::LOCATION, location);
+        }
+        headers.insert("preference-applied", HeaderValue::from_static(RESPOND_ASYNC));
+        response
+    }
+}
+
+/// Whether a `Prefer` header asks for `respond-async`
+pub fn prefers_async(headers: &HeaderMap) -> bool {
+    headers
+        .get_all("prefer")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|preference| {
+            let token = preference.split([';', '=']).next().unwrap_or_default();
+            token.trim().eq_ignore_ascii_case(RESPOND_ASYNC)
+        })
+}
+
+/// Run the request within its group's concurrency limit, shed it or answer it asynchronously
+pub async fn backpressure_middleware(State(group): State<EndpointGroup>, req: Request, next: Next) -> Response {
+    if !group.backpressure.config.enabled {
+        return next.run(req).await;
+    }
+
+    // Results belong to a tenant; without one the preference is ignored
+    let tenant_id = req.extensions().get::<TenantContext>().map(|ctx| ctx.tenant_id.0);
+    if let Some(tenant_id) = tenant_id.filter(|_| prefers_async(req.headers())) {
+        return group.respond_async(req, next, tenant_id).await;
+    }
+
+    let timeout = Duration::from_millis(group.backpressure.config.queue_timeout_ms);
+    let Some(permit) = group.acquire(Some(timeout)).await else {
+        return group.shed();
+    };
+    let response = next.run(req).await;
+    drop(permit);
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::async_results::async_result_routes;
+    use axum::{body::Body, extract::Extension, routing::get, Router};
+    use erp_core::TenantId;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tower::ServiceExt;
+
+    #[derive(Default)]
+    struct InMemoryResults {
+        results: Mutex<HashMap<Uuid, AsyncResult>>,
+    }
+
+    #[async_trait]
+    impl AsyncResultStore for InMemoryResults {
+        async fn create(&self, result: &AsyncResult) -> Result<()> {
+            self.results.lock().unwrap().insert(result.id, result.clone());
+            Ok(())
+        }
+
+        async fn complete(&self, id: Uuid, status_code: u16, content_type: Option<&str>, body: &[u8]) -> Result<()> {
+            if let Some(result) = self.results.lock().unwrap().get_mut(&id) {
+                result.status = AsyncResultStatus::Completed;
+                result.status_code = Some(status_code);
+                result.content_type = content_type.map(str::to_string);
+                result.body = Some(body.to_vec());
+                result.completed_at = Some(Utc::now());
+            }
+            Ok(())
+        }
+
+        async fn fail(&self, id: Uuid, error: &str) -> Result<()> {
+            if let Some(result) = self.results.lock().unwrap().get_mut(&id) {
+                result.status = AsyncResultStatus::Failed;
+                result.error = Some(error.to_string());
+            }
+            Ok(())
+        }
+
+        async fn get(&self, tenant_id: Uuid, id: Uuid, now: DateTime<Utc>) -> Result<Option<AsyncResult>> {
+            let results = self.results.lock().unwrap();
+            Ok(results
+                .get(&id)
+                .filter(|result| result.tenant_id == tenant_id && result.expires_at > now)
+                .cloned())
+        }
+
+        async fn delete_expired(&self, now: DateTime<Utc>) -> Result<u64> {
+            let mut results = self.results.lock().unwrap();
+            let before = results.len();
+            results.retain(|_, result| result.expires_at > now);
+            Ok((before - results.len()) as u64)
+        }
+    }
+
+    /// Counts the requests running at once
+    #[derive(Default)]
+    struct Probe {
+        running: AtomicUsize,
+        most: AtomicUsize,
+    }
+
+    fn backpressure(max_concurrent: usize, queue_timeout_ms: u64) -> Arc<Backpressure> {
+        let config = BackpressureConfig {
+            queue_timeout_ms,
+            groups: HashMap::from([(REPORTS_GROUP.to_string(), max_concurrent)]),
+            ..BackpressureConfig::default()
+        };
+        Arc::new(Backpressure::new(config, Arc::new(InMemoryResults::default()), "test").unwrap())
+    }
+
+    fn app(backpressure: &Arc<Backpressure>, probe: Arc<Probe>, user_id: Uuid, work_ms: u64) -> Router {
+        let report = get(move || async move {
+            let running = probe.running.fetch_add(1, Ordering::SeqCst) + 1;
+            probe.most.fetch_max(running, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(work_ms)).await;
+            probe.running.fetch_sub(1, Ordering::SeqCst);
+            "report"
+        })
+        .layer(axum::middleware::from_fn_with_state(backpressure.group(REPORTS_GROUP), backpressure_middleware));
+
+        let tenant_context = TenantContext {
+            tenant_id: TenantId(Uuid::from_u128(7)),
+            schema_name: "tenant_test".to_string(),
+        };
+        let routes = Router::new()
+            .route("/report", report)
+            .merge(async_result_routes(backpressure.clone()));
+        Router::new()
+            .nest("/api/v1", routes)
+            .layer(Extension(RequestContext::new().with_tenant_context(tenant_context.clone()).with_user_id(user_id)))
+            .layer(Extension(tenant_context))
+    }
+
+    async fn call(app: Router, uri: &str, respond_async: bool) -> Response {
+        let mut request = Request::builder().uri(uri);
+        if respond_async {
+            request = request.header("prefer", "respond-async, wait=5");
+        }
+        app.oneshot(request.body(Body::empty()).unwrap()).await.unwrap()
+    }
+
+    async fn body_string(response: Response) -> String {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    #[test]
+    fn test_prefer_header_parsing() {
+        let mut headers = HeaderMap::new();
+        assert!(!prefers_async(&headers));
+        headers.insert("prefer", HeaderValue::from_static("return=minimal"));
+        assert!(!prefers_async(&headers));
+        headers.append("prefer", HeaderValue::from_static("wait=10, Respond-Async"));
+        assert!(prefers_async(&headers));
+    }
+
+    #[tokio::test]
+    async fn test_group_runs_at_most_its_limit_at_once() {
+        let backpressure = backpressure(2, 5_000);
+        let probe = Arc::new(Probe::default());
+        let app = app(&backpressure, probe.clone(), Uuid::new_v4(), 50);
+
+        let requests: Vec<_> = (0..6)
+            .map(|_| tokio::spawn(call(app.clone(), "/api/v1/report", false)))
+            .collect();
+        for request in requests {
+            assert_eq!(request.await.unwrap().status(), StatusCode::OK);
+        }
+
+        assert_eq!(probe.most.load(Ordering::SeqCst), 2);
+        assert_eq!(backpressure.queue_depth.with_label_values(&[REPORTS_GROUP]).get(), 0);
+        assert_eq!(backpressure.wait_seconds.with_label_values(&[REPORTS_GROUP]).get_sample_count(), 6);
+    }
+
+    #[tokio::test]
+    async fn test_request_waiting_past_the_timeout_is_shed() {
+        let backpressure = backpressure(1, 20);
+        let app = app(&backpressure, Arc::new(Probe::default()), Uuid::new_v4(), 300);
+
+        let running = tokio::spawn(call(app.clone(), "/api/v1/report", false));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let shed = call(app, "/api/v1/report", false).await;
+
+        assert_eq!(shed.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(shed.headers()[header::RETRY_AFTER], "10");
+        assert_eq!(backpressure.shed.with_label_values(&[REPORTS_GROUP]).get(), 1);
+        assert_eq!(running.await.unwrap().status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_respond_async_result_is_fetched_from_its_location() {
+        let backpressure = backpressure(1, 20);
+        let user_id = Uuid::new_v4();
+        let app = app(&backpressure, Arc::new(Probe::default()), user_id, 100);
+
+        // The slot is taken, yet the async request is queued instead of shed
+        let running = tokio::spawn(call(app.clone(), "/api/v1/report", false));
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let accepted = call(app.clone(), "/api/v1/report", true).await;
+        assert_eq!(accepted.status(), StatusCode::ACCEPTED);
+        assert_eq!(accepted.headers()["preference-applied"], "respond-async");
+        let location = accepted.headers()[header::LOCATION].to_str().unwrap().to_string();
+        assert!(location.starts_with(ASYNC_RESULTS_PATH));
+
+        let pending = call(app.clone(), &location, false).await;
+        assert_eq!(pending.status(), StatusCode::ACCEPTED);
+        assert!(body_string(pending).await.contains("\"pending\""));
+
+        let mut result = call(app.clone(), &location, false).await;
+        for _ in 0..100 {
+            if result.status() != StatusCode::ACCEPTED {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            result = call(app.clone(), &location, false).await;
+        }
+        assert_eq!(result.status(), StatusCode::OK);
+        assert_eq!(body_string(result).await, "report");
+        assert_eq!(backpressure.shed.with_label_values(&[REPORTS_GROUP]).get(), 0);
+        assert_eq!(running.await.unwrap().status(), StatusCode::OK);
+
+        // Other users of the tenant do not see it
+        let other = self::app(&backpressure, Arc::new(Probe::default()), Uuid::new_v4(), 0);
+        assert_eq!(call(other, &location, false).await.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,68 @@
+//! Writes the OpenAPI document and the DTO JSON Schemas as static artifacts
+//!
+//! ```text
+//! generate-openapi [--out-dir <dir>] [--check]
+//! ```
+//!
+//! Without `--out-dir` the artifacts go to `docs/api` of the workspace.
+//! `--check` writes nothing and exits non-zero when the artifacts there are
+//! not what this build generates, for CI to catch annotations changed without
+//! regenerating them.
+
+use erp_api::openapi::{artifacts, check_artifacts, spec, write_artifacts, ARTIFACTS_DIR};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    let mut check = false;
+    let mut out_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../..").join(ARTIFACTS_DIR);
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--check" => check = true,
+            "--out-dir" => match args.next() {
+                Some(dir) => out_dir = PathBuf::from(dir),
+                None => {
+                    eprintln!("--out-dir needs a directory");
+                    return ExitCode::from(2);
+                }
+            },
+            other => {
+                eprintln!("unknown argument: {}\nusage: generate-openapi [--out-dir <dir>] [--check]", other);
+                return ExitCode::from(2);
+            }
+        }
+    }
+
+    let files = artifacts(&spec());
+    if check {
+        return match check_artifacts(&out_dir, &files) {
+            Ok(drift) if drift.is_empty() => {
+                println!("OpenAPI artifacts in {} are up to date", out_dir.display());
+                ExitCode::SUCCESS
+            }
+            Ok(drift) => {
+                for d in &drift {
+                    eprintln!("{}", d);
+                }
+                eprintln!("OpenAPI artifacts are out of date, run `cargo run -p erp-api --bin generate-openapi`");
+                ExitCode::FAILURE
+            }
+            Err(e) => {
+                eprintln!("Failed to read {}: {}", out_dir.display(), e);
+                ExitCode::FAILURE
+            }
+        };
+    }
+
+    match write_artifacts(&out_dir, &files) {
+        Ok(()) => {
+            println!("Wrote {} OpenAPI artifacts to {}", files.len(), out_dir.display());
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("Failed to write {}: {}", out_dir.display(), e);
+            ExitCode::FAILURE
+        }
+    }
+}
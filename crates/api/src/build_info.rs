@@ -0,0 +1,88 @@
+//! Build metadata of the running server
+//!
+//! Filled in at compile time by `build.rs` through `erp-build-info`; exposed on `/api/v1/meta/version`
+//! and `/health`, attached to server error responses and stamped on every log
+//! line so reports from any environment can be matched to a commit.
+
+use std::fmt;
+use std::sync::OnceLock;
+use tracing::{Event, Subscriber};
+use tracing_subscriber::{
+    fmt::{format::Writer, FmtContext, FormatEvent, FormatFields},
+    registry::LookupSpan,
+};
+
+pub use erp_build_info::BuildInfo;
+
+pub fn build_info() -> &'static BuildInfo {
+    static BUILD: OnceLock<BuildInfo> = OnceLock::new();
+    BUILD.get_or_init(|| erp_build_info::build_info!())
+}
+
+/// Log line format that prefixes the wrapped format with `build=<short commit>`
+pub struct BuildTaggedFormat<F> {
+    inner: F,
+    commit_short: &'static str,
+}
+
+impl<F> BuildTaggedFormat<F> {
+    pub fn new(inner: F) -> Self {
+        Self { inner, commit_short: &build_info().commit_short }
+    }
+}
+
+impl<S, N, F> FormatEvent<S, N> for BuildTaggedFormat<F>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+    F: FormatEvent<S, N>,
+{
+    fn format_event(&self, ctx: &FmtContext<'_, S, N>, mut writer: Writer<'_>, event: &Event<'_>) -> fmt::Result {
+        write!(writer, "build={} ", self.commit_short)?;
+        self.inner.format_event(ctx, writer, event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use tracing_subscriber::fmt::MakeWriter;
+
+    #[derive(Clone, Default)]
+    struct Captured(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for Captured {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl<'a> MakeWriter<'a> for Captured {
+        type Writer = Captured;
+
+        fn make_writer(&'a self) -> Self::Writer {
+            self.clone()
+        }
+    }
+
+    #[test]
+    fn test_log_lines_carry_short_commit() {
+        let captured = Captured::default();
+        let subscriber = tracing_subscriber::fmt()
+            .event_format(BuildTaggedFormat::new(tracing_subscriber::fmt::format().with_ansi(false)))
+            .with_writer(captured.clone())
+            .finish();
+
+        tracing::subscriber::with_default(subscriber, || tracing::info!("hello"));
+
+        let output = String::from_utf8(captured.0.lock().unwrap().clone()).unwrap();
+        assert!(output.starts_with(&format!("build={} ", build_info().commit_short)));
+        assert!(output.contains("hello"));
+    }
+}
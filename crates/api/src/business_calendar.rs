@@ -0,0 +1,232 @@
+//! # Business Calendars
+//!
+//! Storage of the tenant business calendars (see [`erp_core::calendar`]):
+//!
+//! - `public.business_calendars` holds a tenant's working weekdays and the
+//!   country its holidays were imported from. Tenants without a row work
+//!   every day of the week.
+//! - `public.business_calendar_days` holds the dated exceptions, at most one
+//!   per tenant and date. Importing a country's holidays never replaces a
+//!   manual entry, so manual overrides survive re-imports.
+
+use async_trait::async_trait;
+use chrono::{Duration, NaiveDate, Utc};
+use erp_core::calendar::{
+    holidays, standard_working_days, weekday_from_iso, BusinessCalendar, CalendarDay, DaySource, MAX_SHIFT_DAYS,
+};
+use erp_core::{Error, ErrorCode, Result};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Row};
+use uuid::Uuid;
+
+/// Working weekdays of a tenant
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CalendarSettings {
+    /// ISO weekday numbers, 1 (Monday) to 7 (Sunday)
+    pub working_days: Vec<u32>,
+    /// Country the holidays were last imported from
+    pub country: Option<String>,
+}
+
+impl Default for CalendarSettings {
+    fn default() -> Self {
+        Self {
+            working_days: standard_working_days()
+                .iter()
+                .map(|day| day.number_from_monday())
+                .collect(),
+            country: None,
+        }
+    }
+}
+
+impl CalendarSettings {
+    pub fn validate(&self) -> Result<()> {
+        if self.working_days.is_empty() {
+            return Err(Error::validation("A calendar needs at least one working day"));
+        }
+        if let Some(day) = self.working_days.iter().find(|day| weekday_from_iso(**day).is_none()) {
+            return Err(Error::validation(format!("Working day {} is not an ISO weekday (1-7)", day)));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+pub trait CalendarStore: Send + Sync {
+    /// `None` when the tenant has no calendar
+    async fn settings(&self, tenant_id: Uuid) -> Result<Option<CalendarSettings>>;
+
+    async fn save_settings(&self, tenant_id: Uuid, settings: &CalendarSettings) -> Result<()>;
+
+    /// Exceptions between `from` and `to`, both inclusive
+    async fn days(&self, tenant_id: Uuid, from: NaiveDate, to: NaiveDate) -> Result<Vec<CalendarDay>>;
+
+    /// Insert or replace the exception for the day's date
+    async fn save_day(&self, tenant_id: Uuid, day: &CalendarDay) -> Result<()>;
+
+    async fn delete_day(&self, tenant_id: Uuid, date: NaiveDate) -> Result<bool>;
+
+    /// Store imported holidays, leaving manual entries alone; returns the rows written
+    async fn import_days(&self, tenant_id: Uuid, country: &str, days: &[CalendarDay]) -> Result<u64>;
+}
+
+/// The tenant's calendar with every exception a run on `date` can depend on
+pub async fn calendar_around(store: &dyn CalendarStore, tenant_id: Uuid, date: NaiveDate) -> Result<BusinessCalendar> {
+    let window = Duration::days(MAX_SHIFT_DAYS);
+    let days = store.days(tenant_id, date - window, date + window).await?;
+    let working_days = match store.settings(tenant_id).await? {
+        Some(settings) => settings.working_days,
+        // Without a calendar every weekday works, unless an exception says otherwise
+        None => (1..=7).collect(),
+    };
+    Ok(BusinessCalendar::new(
+        working_days.iter().filter_map(|day| weekday_from_iso(*day)),
+        days,
+    ))
+}
+
+/// Import a country's public holidays of one year into a tenant's calendar
+pub async fn import_holidays(store: &dyn CalendarStore, tenant_id: Uuid, country: &str, year: i32) -> Result<u64> {
+    let days = holidays::calendar_days(country, year)?;
+    store.import_days(tenant_id, &country.to_ascii_uppercase(), &days).await
+}
+
+pub struct PostgresCalendarStore {
+    pool: PgPool,
+}
+
+impl PostgresCalendarStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+fn day_from_row(row: &sqlx::postgres::PgRow) -> Result<CalendarDay> {
+    let source: String = row.get("source");
+    Ok(CalendarDay {
+        date: row.get("day"),
+        label: row.get("label"),
+        working_day: row.get("working_day"),
+        source: DaySource::parse(&source)
+            .ok_or_else(|| Error::new(ErrorCode::DatabaseError, format!("Unknown calendar day source '{}'", source)))?,
+        country: row.get("country"),
+    })
+}
+
+#[async_trait]
+impl CalendarStore for PostgresCalendarStore {
+    async fn settings(&self, tenant_id: Uuid) -> Result<Option<CalendarSettings>> {
+        let row = sqlx::query("SELECT working_days, country FROM public.business_calendars WHERE tenant_id = $1")
+            .bind(tenant_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(row.map(|row| CalendarSettings {
+            working_days: row
+                .get::<Vec<i16>, _>("working_days")
+                .into_iter()
+                .map(|day| day as u32)
+                .collect(),
+            country: row.get("country"),
+        }))
+    }
+
+    async fn save_settings(&self, tenant_id: Uuid, settings: &CalendarSettings) -> Result<()> {
+        let working_days: Vec<i16> = settings.working_days.iter().map(|day| *day as i16).collect();
+        sqlx::query(
+            "INSERT INTO public.business_calendars (tenant_id, working_days, country, updated_at) \
+             VALUES ($1, $2, $3, $4) \
+             ON CONFLICT (tenant_id) DO UPDATE SET \
+                 working_days = EXCLUDED.working_days, country = EXCLUDED.country, updated_at = EXCLUDED.updated_at",
+        )
+        .bind(tenant_id)
+        .bind(&working_days)
+        .bind(&settings.country)
+        .bind(Utc::now())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn days(&self, tenant_id: Uuid, from: NaiveDate, to: NaiveDate) -> Result<Vec<CalendarDay>> {
+        let rows = sqlx::query(
+            "SELECT day, label, working_day, source, country FROM public.business_calendar_days \
+             WHERE tenant_id = $1 AND day BETWEEN $2 AND $3 ORDER BY day",
+        )
+        .bind(tenant_id)
+        .bind(from)
+        .bind(to)
+        .fetch_all(&self.pool)
+        .await?;
+        rows.iter().map(day_from_row).collect()
+    }
+
+    async fn save_day(&self, tenant_id: Uuid, day: &CalendarDay) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO public.business_calendar_days (tenant_id, day, label, working_day, source, country) \
+             VALUES ($1, $2, $3, $4, $5, $6) \
+             ON CONFLICT (tenant_id, day) DO UPDATE SET \
+                 label = EXCLUDED.label, working_day = EXCLUDED.working_day, \
+                 source = EXCLUDED.source, country = EXCLUDED.country",
+        )
+        .bind(tenant_id)
+        .bind(day.date)
+        .bind(&day.label)
+        .bind(day.working_day)
+        .bind(day.source.as_str())
+        .bind(&day.country)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn delete_day(&self, tenant_id: Uuid, date: NaiveDate) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM public.business_calendar_days WHERE tenant_id = $1 AND day = $2")
+            .bind(tenant_id)
+            .bind(date)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn import_days(&self, tenant_id: Uuid, country: &str, days: &[CalendarDay]) -> Result<u64> {
+        let default_days: Vec<i16> = CalendarSettings::default()
+            .working_days
+            .iter()
+            .map(|day| *day as i16)
+            .collect();
+
+        let mut tx = self.pool.begin().await?;
+        // Importing holidays starts a Monday-to-Friday calendar unless the tenant has one
+        sqlx::query(
+            "INSERT INTO public.business_calendars (tenant_id, working_days, country, updated_at) \
+             VALUES ($1, $2, $3, $4) \
+             ON CONFLICT (tenant_id) DO UPDATE SET country = EXCLUDED.country, updated_at = EXCLUDED.updated_at",
+        )
+        .bind(tenant_id)
+        .bind(&default_days)
+        .bind(country)
+        .bind(Utc::now())
+        .execute(&mut *tx)
+        .await?;
+
+        let mut written = 0;
+        for day in days {
+            written += sqlx::query(
+                "INSERT INTO public.business_calendar_days (tenant_id, day, label, working_day, source, country) \
+                 VALUES ($1, $2, $3, false, 'imported', $4) \
+                 ON CONFLICT (tenant_id, day) DO UPDATE SET label = EXCLUDED.label, country = EXCLUDED.country \
+                 WHERE business_calendar_days.source = 'imported'",
+            )
+            .bind(tenant_id)
+            .bind(day.date)
+            .bind(&day.label)
+            .bind(country)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        }
+        tx.commit().await?;
+        Ok(written)
+    }
+}
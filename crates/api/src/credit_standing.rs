@@ -0,0 +1,954 @@
+//! # Customer Credit Standing
+//!
+//! Credit figures of each customer for the external accounting system, which
+//! drives dunning from them (see `migrations/030_customer_credit_standing.sql`):
+//!
+//! - Until invoicing exists the accounting system pushes the customer's open
+//!   items to `POST /api/v1/customers/{id}/open-items`: receivables with their
+//!   due date and open orders holding credit (`reservation`). Items are keyed
+//!   by the external document number; sending a document again updates it in
+//!   place and an unchanged document is left alone, so imports can be
+//!   retried. A settled document is sent with an `open_amount` of zero.
+//! - Every import recomputes the customer's standing while holding the
+//!   customer row: booked exposure (open receivables), active reservations,
+//!   the overdue balance and the days the oldest open receivable is past due.
+//!   When the receivables more than `credit.hold_after_days` past due add up
+//!   to more than `credit.hold_overdue_amount` the customer goes `OnHold`; a
+//!   hold placed this way is lifted, back to the previous status, once that
+//!   balance is settled. Holds set by hand and the stricter statuses
+//!   (`Blocked`, `CashOnly`, `RequiresPrepayment`) are left alone. Every change
+//!   is recorded as a `CreditStatusChanged` customer event.
+//! - `GET /api/v1/customers/credit-status-feed?since=` serves the
+//!   `customer_credit_feed` view through the change log
+//!   ([`SyncEntity::CustomerCredit`](crate::sync::SyncEntity)), so the
+//!   accounting system only fetches customers whose standing, credit limit or
+//!   credit status changed after its cursor.
+//!
+//! Days past due are as of the last import; an import without items only
+//! recomputes them.
+
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, Utc};
+use erp_core::{CreditConfig, Error, ErrorCode, Result};
+use erp_master_data::customer::{CreditStatus, CustomerEvent, EventMetadata};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Row};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Most open items accepted in one import
+pub const MAX_IMPORT_ITEMS: usize = 5_000;
+
+/// Longest external document number
+const MAX_DOCUMENT_NUMBER_CHARS: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OpenItemKind {
+    /// Booked and owed; counts towards exposure and can be overdue
+    #[default]
+    Receivable,
+    /// Open order holding credit until it is invoiced
+    Reservation,
+}
+
+impl OpenItemKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            OpenItemKind::Receivable => "receivable",
+            OpenItemKind::Reservation => "reservation",
+        }
+    }
+
+    fn parse(value: &str) -> Result<Self> {
+        match value {
+            "receivable" => Ok(OpenItemKind::Receivable),
+            "reservation" => Ok(OpenItemKind::Reservation),
+            _ => Err(Error::new(ErrorCode::DatabaseError, format!("Unknown open item kind '{}'", value))),
+        }
+    }
+}
+
+/// One open item as the accounting system sends it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OpenItem {
+    pub external_document_number: String,
+    #[serde(default)]
+    pub kind: OpenItemKind,
+    pub document_date: Option<NaiveDate>,
+    /// Required for receivables
+    pub due_date: Option<NaiveDate>,
+    pub amount: Decimal,
+    /// Still unpaid; zero once settled. Credit notes carry negative amounts
+    pub open_amount: Decimal,
+    pub currency: Option<String>,
+}
+
+impl OpenItem {
+    /// Trim, round amounts to cents and upper-case the currency
+    pub fn normalize_and_validate(mut self) -> Result<Self> {
+        self.external_document_number = self.external_document_number.trim().to_string();
+        if self.external_document_number.is_empty() {
+            return Err(invalid("external_document_number is required"));
+        }
+        if self.external_document_number.chars().count() > MAX_DOCUMENT_NUMBER_CHARS {
+            return Err(invalid(format!(
+                "external_document_number must be at most {} characters",
+                MAX_DOCUMENT_NUMBER_CHARS
+            )));
+        }
+        if self.kind == OpenItemKind::Receivable && self.due_date.is_none() {
+            return Err(invalid(format!(
+                "Receivable {} needs a due_date",
+                self.external_document_number
+            )));
+        }
+
+        self.amount = self.amount.round_dp(2);
+        self.open_amount = self.open_amount.round_dp(2);
+        let same_sign = self.open_amount.is_zero() || self.open_amount.is_sign_negative() == self.amount.is_sign_negative();
+        if !same_sign || self.open_amount.abs() > self.amount.abs() {
+            return Err(invalid(format!(
+                "open_amount of {} must lie between zero and amount",
+                self.external_document_number
+            )));
+        }
+
+        self.currency = match self.currency.as_deref().map(str::trim) {
+            None | Some("") => None,
+            Some(code) if code.len() == 3 && code.chars().all(|c| c.is_ascii_alphabetic()) => {
+                Some(code.to_ascii_uppercase())
+            }
+            Some(code) => return Err(invalid(format!("Invalid currency '{}'", code))),
+        };
+        Ok(self)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ImportOpenItemsRequest {
+    #[serde(default)]
+    pub items: Vec<OpenItem>,
+}
+
+/// An open item as stored, with the customer it was imported for
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredOpenItem {
+    pub customer_id: Uuid,
+    pub item: OpenItem,
+}
+
+/// What importing one item did
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ItemOutcome {
+    Inserted,
+    Updated,
+    Unchanged,
+}
+
+/// Outcome of importing `item` for `customer_id` given the stored item with
+/// the same document number
+pub fn import_outcome(existing: Option<&StoredOpenItem>, customer_id: Uuid, item: &OpenItem) -> Result<ItemOutcome> {
+    match existing {
+        None => Ok(ItemOutcome::Inserted),
+        Some(stored) if stored.customer_id != customer_id => Err(Error::new(
+            ErrorCode::ConflictError,
+            format!("Document {} was imported for another customer", item.external_document_number),
+        )),
+        Some(stored) if stored.item == *item => Ok(ItemOutcome::Unchanged),
+        Some(_) => Ok(ItemOutcome::Updated),
+    }
+}
+
+/// A customer's credit limit and current status, read before recomputing
+#[derive(Debug, Clone)]
+pub struct CustomerCredit {
+    pub credit_limit: Option<Decimal>,
+    pub credit_status: CreditStatus,
+    /// Status restored when an automatic hold is lifted; `None` unless on automatic hold
+    pub status_before_hold: Option<CreditStatus>,
+    pub status_changed_at: Option<DateTime<Utc>>,
+    pub status_reason: Option<String>,
+}
+
+/// A customer's credit figures as of the last import
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CreditStanding {
+    pub credit_limit: Option<Decimal>,
+    pub booked_exposure: Decimal,
+    pub active_reservations: Decimal,
+    /// Exposure and reservations against the credit limit; `None` without a limit
+    pub utilization_percent: Option<Decimal>,
+    #[serde(serialize_with = "serialize_status")]
+    pub credit_status: CreditStatus,
+    #[serde(skip)]
+    pub status_before_hold: Option<CreditStatus>,
+    /// Open receivables past their due date
+    pub overdue_balance: Decimal,
+    pub days_past_due: i64,
+    pub status_changed_at: Option<DateTime<Utc>>,
+    pub status_reason: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CreditStatusChange {
+    #[serde(serialize_with = "serialize_status")]
+    pub previous_status: CreditStatus,
+    #[serde(serialize_with = "serialize_status")]
+    pub new_status: CreditStatus,
+    pub reason: String,
+}
+
+/// Result of one recompute
+#[derive(Debug, Clone)]
+pub struct CreditAssessment {
+    pub standing: CreditStanding,
+    pub change: Option<CreditStatusChange>,
+}
+
+impl CreditAssessment {
+    /// The customer event recording the status change, if there was one
+    pub fn status_event(&self, customer_id: Uuid, changed_by: Uuid) -> Option<CustomerEvent> {
+        let change = self.change.as_ref()?;
+        Some(CustomerEvent::CreditStatusChanged {
+            customer_id,
+            previous_status: change.previous_status.clone(),
+            new_status: change.new_status.clone(),
+            previous_limit: self.standing.credit_limit,
+            new_limit: self.standing.credit_limit,
+            reason: change.reason.clone(),
+            approved_by: changed_by,
+            changed_at: self.standing.status_changed_at.unwrap_or_else(Utc::now),
+        })
+    }
+}
+
+/// When overdue receivables put a customer on hold
+#[derive(Debug, Clone)]
+pub struct CreditPolicy {
+    pub hold_after_days: i64,
+    pub hold_overdue_amount: Decimal,
+}
+
+impl CreditPolicy {
+    pub fn from_config(config: &CreditConfig) -> Self {
+        Self {
+            hold_after_days: config.hold_after_days.max(0),
+            hold_overdue_amount: Decimal::try_from(config.hold_overdue_amount.max(0.0)).unwrap_or(Decimal::ZERO),
+        }
+    }
+
+    /// Standing of a customer with `items` open at `now`
+    pub fn assess(&self, credit: &CustomerCredit, items: &[OpenItem], now: DateTime<Utc>) -> CreditAssessment {
+        let today = now.date_naive();
+        let mut booked_exposure = Decimal::ZERO;
+        let mut active_reservations = Decimal::ZERO;
+        let mut overdue_balance = Decimal::ZERO;
+        let mut hold_balance = Decimal::ZERO;
+        let mut days_past_due = 0;
+
+        for item in items {
+            match item.kind {
+                OpenItemKind::Reservation => active_reservations += item.open_amount,
+                OpenItemKind::Receivable => {
+                    booked_exposure += item.open_amount;
+                    let overdue_days = item.due_date.map_or(0, |due| (today - due).num_days());
+                    if item.open_amount > Decimal::ZERO && overdue_days > 0 {
+                        overdue_balance += item.open_amount;
+                        days_past_due = days_past_due.max(overdue_days);
+                        if overdue_days > self.hold_after_days {
+                            hold_balance += item.open_amount;
+                        }
+                    }
+                }
+            }
+        }
+
+        let should_hold = hold_balance > self.hold_overdue_amount;
+        let previous = credit.credit_status.clone();
+        let transition = match (&previous, &credit.status_before_hold) {
+            (CreditStatus::Excellent | CreditStatus::Good | CreditStatus::Fair | CreditStatus::Poor, _) if should_hold => Some((
+                CreditStatus::OnHold,
+                Some(previous.clone()),
+                format!(
+                    "{} overdue by more than {} days exceeds {}",
+                    hold_balance, self.hold_after_days, self.hold_overdue_amount
+                ),
+            )),
+            (CreditStatus::OnHold, Some(restore)) if !should_hold => {
+                Some((restore.clone(), None, "Overdue balance back within the hold threshold".to_string()))
+            }
+            _ => None,
+        };
+
+        let utilization_percent = credit
+            .credit_limit
+            .filter(|limit| *limit > Decimal::ZERO)
+            .map(|limit| ((booked_exposure + active_reservations) * Decimal::ONE_HUNDRED / limit).round_dp(2));
+        let mut standing = CreditStanding {
+            credit_limit: credit.credit_limit,
+            booked_exposure,
+            active_reservations,
+            utilization_percent,
+            credit_status: previous.clone(),
+            status_before_hold: credit.status_before_hold.clone(),
+            overdue_balance,
+            days_past_due,
+            status_changed_at: credit.status_changed_at,
+            status_reason: credit.status_reason.clone(),
+        };
+
+        let change = transition.map(|(new_status, status_before_hold, reason)| {
+            standing.credit_status = new_status.clone();
+            standing.status_before_hold = status_before_hold;
+            standing.status_changed_at = Some(now);
+            standing.status_reason = Some(reason.clone());
+            CreditStatusChange {
+                previous_status: previous,
+                new_status,
+                reason,
+            }
+        });
+        CreditAssessment { standing, change }
+    }
+}
+
+/// Result of an open items import
+#[derive(Debug, Clone, Serialize)]
+pub struct OpenItemImport {
+    pub inserted: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+    pub standing: CreditStanding,
+    /// Set when the import changed the credit status
+    pub status_change: Option<CreditStatusChange>,
+}
+
+#[async_trait]
+pub trait CreditStandingStore: Send + Sync {
+    /// Upsert the customer's open items and recompute its standing with
+    /// `policy`, holding the customer row throughout; `None` when the tenant
+    /// has no such customer. Expects normalized items with distinct document numbers
+    async fn import_open_items(
+        &self,
+        tenant_id: Uuid,
+        customer_id: Uuid,
+        items: &[OpenItem],
+        imported_by: Uuid,
+        policy: &CreditPolicy,
+    ) -> Result<Option<OpenItemImport>>;
+}
+
+/// Open item imports with the configured hold policy
+pub struct CreditStandingService {
+    store: Arc<dyn CreditStandingStore>,
+    policy: CreditPolicy,
+}
+
+impl CreditStandingService {
+    pub fn new(store: Arc<dyn CreditStandingStore>, config: &CreditConfig) -> Self {
+        Self {
+            store,
+            policy: CreditPolicy::from_config(config),
+        }
+    }
+
+    /// Import the customer's open items and recompute its credit standing
+    pub async fn import(
+        &self,
+        tenant_id: Uuid,
+        customer_id: Uuid,
+        request: ImportOpenItemsRequest,
+        imported_by: Uuid,
+    ) -> Result<OpenItemImport> {
+        if request.items.len() > MAX_IMPORT_ITEMS {
+            return Err(invalid(format!("At most {} open items per import", MAX_IMPORT_ITEMS)));
+        }
+        let items = request
+            .items
+            .into_iter()
+            .map(OpenItem::normalize_and_validate)
+            .collect::<Result<Vec<_>>>()?;
+        let mut seen = HashSet::new();
+        if let Some(duplicate) = items.iter().find(|item| !seen.insert(item.external_document_number.as_str())) {
+            return Err(invalid(format!(
+                "Document {} appears more than once",
+                duplicate.external_document_number
+            )));
+        }
+
+        self.store
+            .import_open_items(tenant_id, customer_id, &items, imported_by, &self.policy)
+            .await?
+            .ok_or_else(|| Error::new(ErrorCode::NotFound, format!("Customer {} not found", customer_id)))
+    }
+}
+
+fn invalid(message: impl Into<String>) -> Error {
+    Error::new(ErrorCode::ValidationFailed, message)
+}
+
+/// Stored form of a credit status, as in the `credit_status` database type
+pub fn status_str(status: &CreditStatus) -> &'static str {
+    match status {
+        CreditStatus::Excellent => "excellent",
+        CreditStatus::Good => "good",
+        CreditStatus::Fair => "fair",
+        CreditStatus::Poor => "poor",
+        CreditStatus::OnHold => "on_hold",
+        CreditStatus::Blocked => "blocked",
+        CreditStatus::CashOnly => "cash_only",
+        CreditStatus::RequiresPrepayment => "requires_prepayment",
+    }
+}
+
+/// Credit statuses in import responses read as they do in the feed
+fn serialize_status<S: serde::Serializer>(status: &CreditStatus, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+    serializer.serialize_str(status_str(status))
+}
+
+fn parse_status(value: &str) -> Result<CreditStatus> {
+    Ok(match value {
+        "excellent" => CreditStatus::Excellent,
+        "good" => CreditStatus::Good,
+        "fair" => CreditStatus::Fair,
+        "poor" => CreditStatus::Poor,
+        "on_hold" => CreditStatus::OnHold,
+        "blocked" => CreditStatus::Blocked,
+        "cash_only" => CreditStatus::CashOnly,
+        "requires_prepayment" => CreditStatus::RequiresPrepayment,
+        _ => return Err(Error::new(ErrorCode::DatabaseError, format!("Unknown credit status '{}'", value))),
+    })
+}
+
+pub struct PostgresCreditStandingStore {
+    pool: PgPool,
+}
+
+impl PostgresCreditStandingStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+const OPEN_ITEM_COLUMNS: &str =
+    "customer_id, external_document_number, kind, document_date, due_date, amount, open_amount, currency";
+
+fn open_item_from_row(row: &sqlx::postgres::PgRow) -> Result<StoredOpenItem> {
+    let kind: String = row.get("kind");
+    Ok(StoredOpenItem {
+        customer_id: row.get("customer_id"),
+        item: OpenItem {
+            external_document_number: row.get("external_document_number"),
+            kind: OpenItemKind::parse(&kind)?,
+            document_date: row.get("document_date"),
+            due_date: row.get("due_date"),
+            amount: row.get("amount"),
+            open_amount: row.get("open_amount"),
+            currency: row.get("currency"),
+        },
+    })
+}
+
+#[async_trait]
+impl CreditStandingStore for PostgresCreditStandingStore {
+    async fn import_open_items(
+        &self,
+        tenant_id: Uuid,
+        customer_id: Uuid,
+        items: &[OpenItem],
+        imported_by: Uuid,
+        policy: &CreditPolicy,
+    ) -> Result<Option<OpenItemImport>> {
+        let mut tx = self.pool.begin().await?;
+        // Concurrent imports for the customer recompute one after the other
+        let Some(row) = sqlx::query(
+            "SELECT c.credit_limit, \
+                 COALESCE(s.credit_status, to_jsonb(c) ->> 'credit_status', 'good') AS credit_status, \
+                 s.status_before_hold, s.status_changed_at, s.status_reason \
+             FROM public.customers c \
+             LEFT JOIN public.customer_credit_standing s ON s.customer_id = c.id \
+             WHERE c.tenant_id = $1 AND c.id = $2 \
+               AND COALESCE((to_jsonb(c) ->> 'is_deleted')::BOOLEAN, false) = false \
+             FOR UPDATE OF c",
+        )
+        .bind(tenant_id)
+        .bind(customer_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        else {
+            return Ok(None);
+        };
+        let credit = CustomerCredit {
+            credit_limit: row.get("credit_limit"),
+            credit_status: parse_status(row.get("credit_status"))?,
+            status_before_hold: row
+                .get::<Option<&str>, _>("status_before_hold")
+                .map(parse_status)
+                .transpose()?,
+            status_changed_at: row.get("status_changed_at"),
+            status_reason: row.get("status_reason"),
+        };
+
+        let numbers: Vec<&str> = items.iter().map(|item| item.external_document_number.as_str()).collect();
+        let existing: HashMap<String, StoredOpenItem> = sqlx::query(&format!(
+            "SELECT {} FROM public.customer_open_items \
+             WHERE tenant_id = $1 AND external_document_number = ANY($2) FOR UPDATE",
+            OPEN_ITEM_COLUMNS
+        ))
+        .bind(tenant_id)
+        .bind(&numbers)
+        .fetch_all(&mut *tx)
+        .await?
+        .iter()
+        .map(|row| open_item_from_row(row).map(|stored| (stored.item.external_document_number.clone(), stored)))
+        .collect::<Result<_>>()?;
+
+        let (mut inserted, mut updated, mut unchanged) = (0, 0, 0);
+        for item in items {
+            let query = match import_outcome(existing.get(&item.external_document_number), customer_id, item)? {
+                ItemOutcome::Unchanged => {
+                    unchanged += 1;
+                    continue;
+                }
+                ItemOutcome::Inserted => {
+                    inserted += 1;
+                    "INSERT INTO public.customer_open_items \
+                         (tenant_id, external_document_number, customer_id, kind, document_date, due_date, \
+                          amount, open_amount, currency, imported_by) \
+                     VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)"
+                }
+                ItemOutcome::Updated => {
+                    updated += 1;
+                    "UPDATE public.customer_open_items SET \
+                         kind = $4, document_date = $5, due_date = $6, amount = $7, open_amount = $8, \
+                         currency = $9, imported_by = $10, updated_at = NOW() \
+                     WHERE tenant_id = $1 AND external_document_number = $2 AND customer_id = $3"
+                }
+            };
+            sqlx::query(query)
+                .bind(tenant_id)
+                .bind(&item.external_document_number)
+                .bind(customer_id)
+                .bind(item.kind.as_str())
+                .bind(item.document_date)
+                .bind(item.due_date)
+                .bind(item.amount)
+                .bind(item.open_amount)
+                .bind(&item.currency)
+                .bind(imported_by)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        let open_items = sqlx::query(&format!(
+            "SELECT {} FROM public.customer_open_items WHERE tenant_id = $1 AND customer_id = $2",
+            OPEN_ITEM_COLUMNS
+        ))
+        .bind(tenant_id)
+        .bind(customer_id)
+        .fetch_all(&mut *tx)
+        .await?
+        .iter()
+        .map(|row| open_item_from_row(row).map(|stored| stored.item))
+        .collect::<Result<Vec<_>>>()?;
+
+        let assessment = policy.assess(&credit, &open_items, Utc::now());
+        let standing = &assessment.standing;
+        // Unchanged standings are not rewritten, so they stay out of the feed
+        sqlx::query(
+            "INSERT INTO public.customer_credit_standing \
+                 (customer_id, tenant_id, credit_status, status_before_hold, booked_exposure, active_reservations, \
+                  overdue_balance, days_past_due, status_changed_at, status_reason, updated_at) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, NOW()) \
+             ON CONFLICT (customer_id) DO UPDATE SET \
+                 credit_status = EXCLUDED.credit_status, status_before_hold = EXCLUDED.status_before_hold, \
+                 booked_exposure = EXCLUDED.booked_exposure, active_reservations = EXCLUDED.active_reservations, \
+                 overdue_balance = EXCLUDED.overdue_balance, days_past_due = EXCLUDED.days_past_due, \
+                 status_changed_at = EXCLUDED.status_changed_at, status_reason = EXCLUDED.status_reason, \
+                 updated_at = EXCLUDED.updated_at \
+             WHERE (customer_credit_standing.credit_status, customer_credit_standing.status_before_hold, \
+                    customer_credit_standing.booked_exposure, customer_credit_standing.active_reservations, \
+                    customer_credit_standing.overdue_balance, customer_credit_standing.days_past_due) \
+                 IS DISTINCT FROM (EXCLUDED.credit_status, EXCLUDED.status_before_hold, EXCLUDED.booked_exposure, \
+                    EXCLUDED.active_reservations, EXCLUDED.overdue_balance, EXCLUDED.days_past_due)",
+        )
+        .bind(customer_id)
+        .bind(tenant_id)
+        .bind(status_str(&standing.credit_status))
+        .bind(standing.status_before_hold.as_ref().map(status_str))
+        .bind(standing.booked_exposure)
+        .bind(standing.active_reservations)
+        .bind(standing.overdue_balance)
+        .bind(standing.days_past_due as i32)
+        .bind(standing.status_changed_at)
+        .bind(&standing.status_reason)
+        .execute(&mut *tx)
+        .await?;
+
+        if let Some(event) = assessment.status_event(customer_id, imported_by) {
+            let sequence: i64 = sqlx::query_scalar(
+                "SELECT COALESCE(MAX(sequence_number), 0) + 1 FROM customer_events WHERE aggregate_id = $1 AND tenant_id = $2",
+            )
+            .bind(customer_id)
+            .bind(tenant_id)
+            .fetch_one(&mut *tx)
+            .await?;
+            let metadata = EventMetadata::new(customer_id, tenant_id, sequence, Some(imported_by));
+            sqlx::query(
+                "INSERT INTO customer_events \
+                     (event_id, aggregate_id, tenant_id, sequence_number, event_type, \
+                      event_data, metadata, occurred_at, recorded_at, user_id) \
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)",
+            )
+            .bind(metadata.event_id)
+            .bind(customer_id)
+            .bind(tenant_id)
+            .bind(sequence)
+            .bind(event.event_type())
+            .bind(serde_json::to_value(&event)?)
+            .bind(serde_json::to_value(&metadata)?)
+            .bind(metadata.occurred_at)
+            .bind(metadata.recorded_at)
+            .bind(imported_by)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(Some(OpenItemImport {
+            inserted,
+            updated,
+            unchanged,
+            standing: assessment.standing,
+            status_change: assessment.change,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::{ChangeLogStore, ChangeOperation, ChangeRecord, SyncEntity, SyncEnvelope, SyncService};
+    use chrono::Duration;
+    use erp_core::{SyncConfig, TenantContext, TenantId};
+    use serde_json::{json, Value};
+    use std::sync::Mutex;
+
+
+    /// Open items, standings and the change log as the migration's tables and triggers keep them
+    #[derive(Default)]
+    struct MemoryStore {
+        customers: Mutex<HashMap<Uuid, CustomerCredit>>,
+        items: Mutex<Vec<StoredOpenItem>>,
+        log: Mutex<Vec<(i64, Uuid)>>,
+        feed: Mutex<HashMap<Uuid, Value>>,
+        events: Mutex<Vec<CustomerEvent>>,
+    }
+
+    impl MemoryStore {
+        fn with_customer(customer_id: Uuid, credit_limit: Decimal) -> Self {
+            let store = Self::default();
+            store.add_customer(customer_id, credit_limit);
+            store
+        }
+
+        fn add_customer(&self, customer_id: Uuid, credit_limit: Decimal) {
+            self.customers.lock().unwrap().insert(
+                customer_id,
+                CustomerCredit {
+                    credit_limit: Some(credit_limit),
+                    credit_status: CreditStatus::Good,
+                    status_before_hold: None,
+                    status_changed_at: None,
+                    status_reason: None,
+                },
+            );
+        }
+    }
+
+    #[async_trait]
+    impl CreditStandingStore for MemoryStore {
+        async fn import_open_items(
+            &self,
+            _tenant_id: Uuid,
+            customer_id: Uuid,
+            items: &[OpenItem],
+            imported_by: Uuid,
+            policy: &CreditPolicy,
+        ) -> Result<Option<OpenItemImport>> {
+            let mut customers = self.customers.lock().unwrap();
+            let Some(credit) = customers.get_mut(&customer_id) else {
+                return Ok(None);
+            };
+            let mut stored_items = self.items.lock().unwrap();
+
+            let (mut inserted, mut updated, mut unchanged) = (0, 0, 0);
+            for item in items {
+                let position = stored_items
+                    .iter()
+                    .position(|stored| stored.item.external_document_number == item.external_document_number);
+                let stored = StoredOpenItem { customer_id, item: item.clone() };
+                match import_outcome(position.map(|i| &stored_items[i]), customer_id, item)? {
+                    ItemOutcome::Inserted => {
+                        inserted += 1;
+                        stored_items.push(stored);
+                    }
+                    ItemOutcome::Updated => {
+                        updated += 1;
+                        stored_items[position.unwrap()] = stored;
+                    }
+                    ItemOutcome::Unchanged => unchanged += 1,
+                }
+            }
+
+            let open_items: Vec<OpenItem> = stored_items
+                .iter()
+                .filter(|stored| stored.customer_id == customer_id)
+                .map(|stored| stored.item.clone())
+                .collect();
+            let assessment = policy.assess(credit, &open_items, Utc::now());
+            let standing = &assessment.standing;
+            let row = json!({
+                "id": customer_id,
+                "credit_status": status_str(&standing.credit_status),
+                "booked_exposure": standing.booked_exposure,
+                "utilization_percent": standing.utilization_percent,
+                "days_past_due": standing.days_past_due,
+                "status_changed_at": standing.status_changed_at,
+                "status_reason": standing.status_reason,
+            });
+            let mut feed = self.feed.lock().unwrap();
+            if feed.get(&customer_id) != Some(&row) {
+                feed.insert(customer_id, row);
+                let mut log = self.log.lock().unwrap();
+                let seq = log.last().map_or(0, |entry| entry.0) + 1;
+                log.push((seq, customer_id));
+            }
+
+            credit.credit_status = standing.credit_status.clone();
+            credit.status_before_hold = standing.status_before_hold.clone();
+            credit.status_changed_at = standing.status_changed_at;
+            credit.status_reason = standing.status_reason.clone();
+            self.events.lock().unwrap().extend(assessment.status_event(customer_id, imported_by));
+
+            Ok(Some(OpenItemImport {
+                inserted,
+                updated,
+                unchanged,
+                standing: assessment.standing,
+                status_change: assessment.change,
+            }))
+        }
+    }
+
+    #[async_trait]
+    impl ChangeLogStore for MemoryStore {
+        async fn horizon(&self, _tenant_id: Uuid, _entity: SyncEntity) -> std::result::Result<i64, sqlx::Error> {
+            Ok(0)
+        }
+
+        async fn latest_seq(&self, _tenant_id: Uuid, _entity: SyncEntity) -> std::result::Result<i64, sqlx::Error> {
+            Ok(self.log.lock().unwrap().last().map_or(0, |entry| entry.0))
+        }
+
+        async fn changes_after(
+            &self,
+            _tenant_id: Uuid,
+            entity: SyncEntity,
+            since: i64,
+        ) -> std::result::Result<Vec<ChangeRecord>, sqlx::Error> {
+            assert_eq!(entity, SyncEntity::CustomerCredit);
+            Ok(self
+                .log
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|entry| entry.0 > since)
+                .map(|entry| ChangeRecord {
+                    change_seq: entry.0,
+                    entity_id: entry.1,
+                    operation: ChangeOperation::Updated,
+                })
+                .collect())
+        }
+
+        async fn load_entities(
+            &self,
+            _tenant: &TenantContext,
+            _entity: SyncEntity,
+            ids: &[Uuid],
+        ) -> std::result::Result<HashMap<Uuid, Value>, sqlx::Error> {
+            let feed = self.feed.lock().unwrap();
+            Ok(ids.iter().filter_map(|id| feed.get(id).map(|row| (*id, row.clone()))).collect())
+        }
+
+        async fn count_before(&self, _tenant_id: Uuid, _cutoff: DateTime<Utc>) -> std::result::Result<u64, sqlx::Error> {
+            Ok(0)
+        }
+
+        async fn compact(&self, _tenant_id: Uuid, _cutoff: DateTime<Utc>, _limit: i64) -> std::result::Result<u64, sqlx::Error> {
+            Ok(0)
+        }
+    }
+
+    fn receivable(number: &str, days_overdue: i64, amount: i64, open_amount: i64) -> OpenItem {
+        OpenItem {
+            external_document_number: number.to_string(),
+            kind: OpenItemKind::Receivable,
+            document_date: None,
+            due_date: Some(Utc::now().date_naive() - Duration::days(days_overdue)),
+            amount: Decimal::from(amount),
+            open_amount: Decimal::from(open_amount),
+            currency: Some("eur".to_string()),
+        }
+    }
+
+    fn reservation(number: &str, amount: i64) -> OpenItem {
+        OpenItem {
+            external_document_number: number.to_string(),
+            kind: OpenItemKind::Reservation,
+            document_date: None,
+            due_date: None,
+            amount: Decimal::from(amount),
+            open_amount: Decimal::from(amount),
+            currency: None,
+        }
+    }
+
+    fn config() -> CreditConfig {
+        CreditConfig {
+            hold_after_days: 30,
+            hold_overdue_amount: 100.0,
+        }
+    }
+
+    async fn feed_since(store: &Arc<MemoryStore>, since: i64) -> (Vec<SyncEnvelope>, i64) {
+        let page = SyncService::new(store.clone(), SyncConfig::default())
+            .changes_since(
+                &TenantContext { tenant_id: TenantId(Uuid::nil()), schema_name: "tenant_test".to_string() },
+                SyncEntity::CustomerCredit,
+                since,
+            )
+            .await
+            .unwrap();
+        (page.changes, page.next_cursor)
+    }
+
+    #[tokio::test]
+    async fn test_import_recomputes_status_and_reaches_the_feed() {
+        let (customer, user) = (Uuid::new_v4(), Uuid::new_v4());
+        let store = Arc::new(MemoryStore::with_customer(customer, Decimal::from(10_000)));
+        let service = CreditStandingService::new(store.clone(), &config());
+
+        let request = ImportOpenItemsRequest {
+            items: vec![receivable("INV-1", 45, 4_000, 3_000), receivable("INV-2", 5, 1_000, 1_000), reservation("SO-7", 1_000)],
+        };
+        let import = service.import(Uuid::nil(), customer, request, user).await.unwrap();
+        assert_eq!(import.inserted, 3);
+        assert_eq!(import.standing.booked_exposure, Decimal::from(4_000));
+        assert_eq!(import.standing.active_reservations, Decimal::from(1_000));
+        assert_eq!(import.standing.utilization_percent, Some(Decimal::from(50)));
+        assert_eq!(import.standing.overdue_balance, Decimal::from(4_000));
+        assert_eq!(import.standing.days_past_due, 45);
+        assert_eq!(import.standing.credit_status, CreditStatus::OnHold);
+
+        let events = store.events.lock().unwrap().clone();
+        assert!(matches!(
+            events.as_slice(),
+            [CustomerEvent::CreditStatusChanged { previous_status: CreditStatus::Good, new_status: CreditStatus::OnHold, approved_by, .. }]
+                if *approved_by == user
+        ));
+
+        let (changes, cursor) = feed_since(&store, 0).await;
+        let [SyncEnvelope::Updated { id, data, .. }] = changes.as_slice() else {
+            panic!("expected one change, got {:?}", changes);
+        };
+        assert_eq!(*id, customer);
+        assert_eq!(data["credit_status"], "on_hold");
+        assert_eq!(data["days_past_due"], 45);
+        assert!(data["status_changed_at"].is_string());
+        assert!(data["status_reason"].as_str().unwrap().contains("more than 30 days"));
+
+        // The old invoice is paid: the automatic hold lifts
+        let request = ImportOpenItemsRequest { items: vec![receivable("INV-1", 45, 4_000, 0)] };
+        let import = service.import(Uuid::nil(), customer, request, user).await.unwrap();
+        assert_eq!((import.inserted, import.updated), (0, 1));
+        assert_eq!(import.standing.credit_status, CreditStatus::Good);
+        assert_eq!(import.standing.days_past_due, 5);
+        assert_eq!(store.events.lock().unwrap().len(), 2);
+
+        let (changes, _) = feed_since(&store, cursor).await;
+        assert!(matches!(changes.as_slice(), [SyncEnvelope::Updated { data, .. }] if data["credit_status"] == "good"));
+    }
+
+    #[tokio::test]
+    async fn test_reimporting_the_same_documents_changes_nothing() {
+        let (customer, user) = (Uuid::new_v4(), Uuid::new_v4());
+        let store = Arc::new(MemoryStore::with_customer(customer, Decimal::from(10_000)));
+        let service = CreditStandingService::new(store.clone(), &config());
+        let items = || ImportOpenItemsRequest { items: vec![receivable(" INV-1 ", 60, 500, 500), reservation("SO-1", 200)] };
+
+        let first = service.import(Uuid::nil(), customer, items(), user).await.unwrap();
+        assert_eq!(first.inserted, 2);
+        assert!(first.status_change.is_some());
+        let (_, cursor) = feed_since(&store, 0).await;
+
+        let again = service.import(Uuid::nil(), customer, items(), user).await.unwrap();
+        assert_eq!((again.inserted, again.updated, again.unchanged), (0, 0, 2));
+        assert_eq!(again.standing, first.standing);
+        assert!(again.status_change.is_none());
+        assert_eq!(store.events.lock().unwrap().len(), 1);
+        assert!(feed_since(&store, cursor).await.0.is_empty());
+
+        // The same document number for another customer is refused
+        let other = Uuid::new_v4();
+        store.add_customer(other, Decimal::from(10_000));
+        let err = service.import(Uuid::nil(), other, items(), user).await.unwrap_err();
+        assert_eq!(err.code, ErrorCode::ConflictError);
+    }
+
+    #[test]
+    fn test_manual_statuses_are_left_alone() {
+        let policy = CreditPolicy::from_config(&config());
+        let overdue = [receivable("INV-1", 90, 5_000, 5_000)];
+        let credit = |status: CreditStatus| CustomerCredit {
+            credit_limit: None,
+            credit_status: status,
+            status_before_hold: None,
+            status_changed_at: None,
+            status_reason: None,
+        };
+
+        for status in [CreditStatus::Blocked, CreditStatus::CashOnly, CreditStatus::RequiresPrepayment, CreditStatus::OnHold] {
+            let assessment = policy.assess(&credit(status.clone()), &overdue, Utc::now());
+            assert_eq!(assessment.standing.credit_status, status);
+            assert!(assessment.change.is_none());
+            assert_eq!(assessment.standing.utilization_percent, None);
+        }
+
+        // A hold set by hand stays once the balance is paid
+        let assessment = policy.assess(&credit(CreditStatus::OnHold), &[], Utc::now());
+        assert!(assessment.change.is_none());
+
+        // Below the amount threshold nothing happens
+        let small = [receivable("INV-2", 90, 100, 100)];
+        assert!(policy.assess(&credit(CreditStatus::Fair), &small, Utc::now()).change.is_none());
+    }
+
+    #[test]
+    fn test_open_items_are_validated() {
+        let mut missing_due = receivable("INV-1", 0, 10, 10);
+        missing_due.due_date = None;
+        assert!(missing_due.normalize_and_validate().is_err());
+        assert!(receivable("INV-1", 0, 10, 20).normalize_and_validate().is_err());
+        assert!(receivable("INV-1", 0, -10, 5).normalize_and_validate().is_err());
+        assert!(receivable("  ", 0, 10, 5).normalize_and_validate().is_err());
+
+        let credit_note = receivable(" CN-1 ", 0, -10, -4).normalize_and_validate().unwrap();
+        assert_eq!(credit_note.external_document_number, "CN-1");
+        assert_eq!(credit_note.currency.as_deref(), Some("EUR"));
+    }
+}
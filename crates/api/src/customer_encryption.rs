@@ -0,0 +1,99 @@
+//! # Customer Field Encryption
+//!
+//! Builds the cipher for customer tax numbers and bank accounts from
+//! `customer_encryption` (see [`erp_core::CustomerEncryptionConfig`]) and runs
+//! the backfill that seals rows written before encryption was enabled or with
+//! a retired key. The backfill runs every
+//! `customer_encryption.backfill_interval_seconds` until the run for the
+//! current key completes; making another key current starts a new run.
+
+use erp_core::{Config, Error, ErrorCode, Result};
+use erp_master_data::customer::{BlindIndexer, CustomerEncryptionBackfill, CustomerFieldCipher, PostgresBackfillStore};
+use erp_master_data::security::{KeyRing, VersionedFieldEncryption};
+use sqlx::PgPool;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// Key id of `security.aes_encryption_key` when no keys are configured
+pub const DEFAULT_KEY_ID: &str = "v1";
+
+/// Batches per backfill tick, so one tick never holds the job for long
+const BATCHES_PER_TICK: usize = 20;
+
+fn config_error(e: impl std::fmt::Display) -> Error {
+    Error::new(ErrorCode::ConfigurationError, format!("customer_encryption: {}", e))
+}
+
+/// Cipher for the configured keys, `None` when encryption is disabled
+pub fn build_cipher(config: &Config) -> Result<Option<Arc<CustomerFieldCipher>>> {
+    let settings = &config.customer_encryption;
+    if !settings.enabled {
+        return Ok(None);
+    }
+
+    let mut keys = settings.keys.clone();
+    if keys.is_empty() {
+        keys.insert(DEFAULT_KEY_ID.to_string(), config.security.aes_encryption_key.clone());
+    }
+    let ring = KeyRing::new(
+        keys.into_iter().map(|(id, key)| (id, key.into_bytes())).collect(),
+        &settings.current_key_id,
+    )
+    .map_err(config_error)?;
+
+    let blind_index = match &settings.blind_index_key {
+        Some(key) => BlindIndexer::new(key.as_bytes()),
+        None => BlindIndexer::derived_from(config.security.aes_encryption_key.as_bytes()),
+    }
+    .map_err(config_error)?;
+
+    Ok(Some(Arc::new(CustomerFieldCipher::new(
+        Arc::new(VersionedFieldEncryption::new(ring)),
+        blind_index,
+    ))))
+}
+
+/// Background backfill of the sensitive customer columns
+pub struct CustomerEncryptionJob {
+    backfill: CustomerEncryptionBackfill,
+    interval_seconds: u64,
+}
+
+impl CustomerEncryptionJob {
+    pub fn new(pool: PgPool, cipher: Arc<CustomerFieldCipher>, config: &Config) -> Self {
+        let settings = &config.customer_encryption;
+        Self {
+            backfill: CustomerEncryptionBackfill::new(
+                Arc::new(PostgresBackfillStore::new(pool)),
+                cipher,
+                settings.backfill_batch_size,
+            ),
+            interval_seconds: settings.backfill_interval_seconds,
+        }
+    }
+
+    /// Run the backfill in the background until the current key's run completes
+    pub fn spawn(self: &Arc<Self>) -> tokio::task::JoinHandle<()> {
+        let job = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(std::time::Duration::from_secs(job.interval_seconds.max(1)));
+            loop {
+                ticker.tick().await;
+                match job.backfill.run(BATCHES_PER_TICK).await {
+                    Ok(progress) if progress.completed_at.is_some() => {
+                        info!(
+                            "Customer encryption backfill {} completed: {} rows scanned, {} encrypted",
+                            progress.job, progress.rows_scanned, progress.rows_encrypted
+                        );
+                        break;
+                    }
+                    Ok(progress) => info!(
+                        "Customer encryption backfill {}: {} rows scanned, {} encrypted",
+                        progress.job, progress.rows_scanned, progress.rows_encrypted
+                    ),
+                    Err(e) => warn!("Customer encryption backfill failed: {}", e),
+                }
+            }
+        })
+    }
+}
@@ -8,6 +8,8 @@ use serde_json::json;
 use std::env;
 use tracing::{error, warn};
 
+use crate::build_info::build_info;
+
 /// API error wrapper that provides secure error handling and response sanitization.
 /// 
 /// This wrapper ensures that sensitive information is never exposed in API responses,
@@ -155,6 +157,44 @@ impl IntoResponse for ApiError {
             });
         }
 
+        // Tie server errors to the exact build so reports can be matched to a commit
+        if status_code.is_server_error() {
+            if let Some(error_obj) = response_json.get_mut("error") {
+                error_obj["build"] = json!(build_info().commit_short);
+            }
+        }
+
         (status_code, Json(response_json)).into_response()
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn body_json(response: Response) -> serde_json::Value {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_server_error_carries_build() {
+        for environment in ["development", "production"] {
+            let error = Error::new(erp_core::ErrorCode::InternalServerError, "boom");
+            let response = ApiError::new_with_environment(error, environment.to_string()).into_response();
+
+            assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+            let body = body_json(response).await;
+            assert_eq!(body["error"]["build"], build_info().commit_short, "environment {}", environment);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_client_error_omits_build() {
+        let error = Error::new(erp_core::ErrorCode::ValidationFailed, "bad input");
+        let response = ApiError::new_with_environment(error, "production".to_string()).into_response();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert!(body_json(response).await["error"].get("build").is_none());
+    }
 }
\ No newline at end of file
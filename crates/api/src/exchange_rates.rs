@@ -0,0 +1,204 @@
+//! # ECB Reference Rates
+//!
+//! With `exchange_rates.ecb_enabled`, the euro reference rates the European
+//! Central Bank publishes on working days are fetched from
+//! `exchange_rates.ecb_url` at startup and every day at `run_at_hour_utc`, and
+//! stored as rates of no tenant (see [`erp_master_data::currency`]). Reports
+//! fall back to them for pairs a tenant has not entered a rate for.
+//!
+//! The daily file holds one day, the 90-day history file (`eurofxref-hist-90d.xml`)
+//! the last three months; both are read the same way, so pointing `ecb_url`
+//! at the history file once backfills missed days. Rates of a day already
+//! stored are replaced.
+
+use chrono::{NaiveDate, Utc};
+use erp_core::outbound::OutboundClient;
+use erp_core::{Error, ErrorCode, ExchangeRatesConfig, Result};
+use erp_master_data::currency::{ExchangeRate, ExchangeRateRepository, RateSource, EURO};
+use rust_decimal::Decimal;
+use std::str::FromStr;
+use std::sync::Arc;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::retention::next_run;
+
+/// Outbound integration the reference rates are fetched through
+pub const ECB_RATES_INTEGRATION: &str = "ecb_rates";
+
+/// One euro reference rate: 1 EUR = `rate` `currency` on `date`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReferenceRate {
+    pub date: NaiveDate,
+    pub currency: String,
+    pub rate: Decimal,
+}
+
+/// Value of `name="…"` within a tag, if present
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let start = tag.find(&format!("{}=", name))? + name.len() + 1;
+    let quote = tag[start..].chars().next().filter(|c| *c == '"' || *c == '\'')?;
+    let value = &tag[start + 1..];
+    Some(&value[..value.find(quote)?])
+}
+
+/// Reads the rates of every `<Cube time="…">` block of an ECB reference rate file
+pub fn parse_reference_rates(xml: &str) -> Result<Vec<ReferenceRate>> {
+    let invalid = |message: String| Error::new(ErrorCode::ExternalServiceError, message);
+    let mut rates = Vec::new();
+    let mut date = None;
+
+    for tag in xml.split('<').skip(1) {
+        let Some(tag) = tag.strip_prefix("Cube") else {
+            continue;
+        };
+        let tag = &tag[..tag.find('>').unwrap_or(tag.len())];
+        if let Some(time) = attribute(tag, "time") {
+            date = Some(
+                NaiveDate::parse_from_str(time, "%Y-%m-%d")
+                    .map_err(|_| invalid(format!("Invalid reference rate date: {}", time)))?,
+            );
+        } else if let (Some(currency), Some(rate)) = (attribute(tag, "currency"), attribute(tag, "rate")) {
+            let date = date.ok_or_else(|| invalid(format!("Reference rate for {} outside a dated block", currency)))?;
+            let rate = Decimal::from_str(rate)
+                .ok()
+                .filter(|rate| *rate > Decimal::ZERO)
+                .ok_or_else(|| invalid(format!("Invalid reference rate for {}: {}", currency, rate)))?;
+            rates.push(ReferenceRate {
+                date,
+                currency: currency.to_ascii_uppercase(),
+                rate,
+            });
+        }
+    }
+
+    if rates.is_empty() {
+        return Err(invalid("No reference rates in the ECB response".to_string()));
+    }
+    Ok(rates)
+}
+
+/// Fetches the ECB reference rates and publishes them for all tenants
+pub struct EcbRateFetcher {
+    client: OutboundClient,
+    repository: Arc<dyn ExchangeRateRepository>,
+    config: ExchangeRatesConfig,
+}
+
+impl EcbRateFetcher {
+    pub fn new(client: OutboundClient, repository: Arc<dyn ExchangeRateRepository>, config: ExchangeRatesConfig) -> Self {
+        Self {
+            client,
+            repository,
+            config,
+        }
+    }
+
+    /// Fetch the rates once; returns how many were stored
+    pub async fn fetch(&self) -> Result<u64> {
+        let response = self.client.send(self.client.get(&self.config.ecb_url)).await?;
+        if !response.status().is_success() {
+            return Err(Error::new(
+                ErrorCode::ExternalServiceError,
+                format!("ECB reference rates answered {}", response.status()),
+            ));
+        }
+        let body = response.text().await.map_err(|e| {
+            Error::new(ErrorCode::ExternalServiceError, format!("Reading ECB reference rates failed: {}", e))
+        })?;
+
+        let now = Utc::now();
+        let rates: Vec<ExchangeRate> = parse_reference_rates(&body)?
+            .into_iter()
+            .map(|rate| ExchangeRate {
+                id: Uuid::new_v4(),
+                tenant_id: None,
+                from_currency: EURO.to_string(),
+                to_currency: rate.currency,
+                rate: rate.rate,
+                effective_date: rate.date,
+                source: RateSource::Ecb,
+                created_at: now,
+                updated_at: now,
+                created_by: None,
+                updated_by: None,
+            })
+            .collect();
+
+        self.repository
+            .publish_rates(&rates)
+            .await
+            .map_err(|e| Error::new(ErrorCode::DatabaseError, e.to_string()))
+    }
+
+    async fn fetch_logged(&self) {
+        match self.fetch().await {
+            Ok(stored) => info!("Stored {} ECB reference rates", stored),
+            Err(e) => warn!("Fetching ECB reference rates failed: {}", e),
+        }
+    }
+
+    /// Fetch at startup and then daily at `run_at_hour_utc`; `None` when disabled
+    pub fn spawn(self: &Arc<Self>) -> Option<tokio::task::JoinHandle<()>> {
+        if !self.config.ecb_enabled {
+            return None;
+        }
+
+        let fetcher = Arc::clone(self);
+        Some(tokio::spawn(async move {
+            fetcher.fetch_logged().await;
+            loop {
+                let now = Utc::now();
+                let wait = (next_run(now, fetcher.config.run_at_hour_utc) - now)
+                    .to_std()
+                    .unwrap_or_default();
+                tokio::time::sleep(wait).await;
+                fetcher.fetch_logged().await;
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_parse_reference_rates_reads_every_day() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gesmes:Envelope xmlns:gesmes="http://www.gesmes.org/xml/2002-08-01" xmlns="http://www.ecb.int/vocabulary/2002-08-01/eurofxref">
+    <gesmes:subject>Reference rates</gesmes:subject>
+    <Cube>
+        <Cube time='2025-03-04'>
+            <Cube currency='USD' rate='1.0625'/>
+            <Cube currency='CHF' rate='0.9415'/>
+        </Cube>
+        <Cube time="2025-03-03">
+            <Cube currency="USD" rate="1.0465"/>
+        </Cube>
+    </Cube>
+</gesmes:Envelope>"#;
+
+        let rates = parse_reference_rates(xml).unwrap();
+
+        assert_eq!(
+            rates,
+            vec![
+                ReferenceRate { date: date("2025-03-04"), currency: "USD".to_string(), rate: Decimal::from_str("1.0625").unwrap() },
+                ReferenceRate { date: date("2025-03-04"), currency: "CHF".to_string(), rate: Decimal::from_str("0.9415").unwrap() },
+                ReferenceRate { date: date("2025-03-03"), currency: "USD".to_string(), rate: Decimal::from_str("1.0465").unwrap() },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_reference_rates_rejects_broken_files() {
+        assert!(parse_reference_rates("<html>Service unavailable</html>").is_err());
+        assert!(parse_reference_rates(r#"<Cube><Cube currency="USD" rate="1.05"/></Cube>"#).is_err());
+        assert!(parse_reference_rates(r#"<Cube time="2025-03-04"><Cube currency="USD" rate="-1"/></Cube>"#).is_err());
+    }
+}
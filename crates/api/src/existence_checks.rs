@@ -0,0 +1,365 @@
+//! # Existence Checks
+//!
+//! Forms ask whether an email, SKU or customer number is already taken
+//! through `GET /api/v1/users/exists?email=`, `/products/exists?sku=` and
+//! `/customers/exists?customer_number=`. The answer is `{"exists": bool}`
+//! and the `X-Exists` header, so `HEAD` works as well; nothing of the entity
+//! is returned. Each lookup is `SELECT 1 ... LIMIT 1` on the unique index
+//! of the tenant's table.
+//!
+//! The checks are rate limited per user apart from the token's general
+//! limit, at `existence_checks.requests_per_minute` across the three, so
+//! they cannot be used to harvest addresses or numbers. An email check
+//! always answers `existence_checks.email_response_ms` after it started,
+//! whether the address has an account or not. SKU and customer number
+//! answers carry `Cache-Control: private, max-age` and an ETag of the answer;
+//! email answers are `no-store`.
+
+use async_trait::async_trait;
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use erp_core::api_usage::RateLimitStanding;
+use erp_core::{DatabasePool, ExistenceChecksConfig, RequestContext, Result, TenantContext};
+use serde_json::json;
+use std::sync::Arc;
+use tokio::time::{Duration, Instant};
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::api_usage::{rate_limited, set_headers, RateLimitCounter};
+
+/// Redis key prefix of the per-user counters
+pub const EXISTENCE_RATE_LIMIT_PREFIX: &str = "rate_limit:exists";
+
+/// Whether a value is taken, without loading what took it
+#[async_trait]
+pub trait ExistenceLookup: Send + Sync {
+    /// A user of the tenant has this email
+    async fn email_exists(&self, tenant: &TenantContext, email: &str) -> Result<bool>;
+    /// A product of the tenant has this SKU
+    async fn sku_exists(&self, tenant_id: Uuid, sku: &str) -> Result<bool>;
+    /// A customer of the tenant, deleted or not, has this number
+    async fn customer_number_exists(&self, tenant_id: Uuid, customer_number: &str) -> Result<bool>;
+}
+
+/// Lookups on `users` of the tenant schema, `products` and `customers`
+pub struct PostgresExistenceLookup {
+    db: DatabasePool,
+}
+
+impl PostgresExistenceLookup {
+    pub fn new(db: DatabasePool) -> Self {
+        Self { db }
+    }
+}
+
+#[async_trait]
+impl ExistenceLookup for PostgresExistenceLookup {
+    async fn email_exists(&self, tenant: &TenantContext, email: &str) -> Result<bool> {
+        let pool = self.db.get_tenant_pool(tenant).await?;
+        let found: Option<i32> = sqlx::query_scalar("SELECT 1 FROM users WHERE email = $1 LIMIT 1")
+            .bind(email)
+            .fetch_optional(pool.get())
+            .await?;
+        Ok(found.is_some())
+    }
+
+    async fn sku_exists(&self, tenant_id: Uuid, sku: &str) -> Result<bool> {
+        let found: Option<i32> = sqlx::query_scalar("SELECT 1 FROM products WHERE tenant_id = $1 AND sku = $2 LIMIT 1")
+            .bind(tenant_id)
+            .bind(sku)
+            .fetch_optional(&self.db.main_pool)
+            .await?;
+        Ok(found.is_some())
+    }
+
+    async fn customer_number_exists(&self, tenant_id: Uuid, customer_number: &str) -> Result<bool> {
+        // Numbers of deleted customers stay taken under unique_tenant_customer_number
+        let found: Option<i32> =
+            sqlx::query_scalar("SELECT 1 FROM customers WHERE tenant_id = $1 AND customer_number = $2 LIMIT 1")
+                .bind(tenant_id)
+                .bind(customer_number)
+                .fetch_optional(&self.db.main_pool)
+                .await?;
+        Ok(found.is_some())
+    }
+}
+
+pub struct ExistenceChecks {
+    lookup: Arc<dyn ExistenceLookup>,
+    limiter: Arc<dyn RateLimitCounter>,
+    config: ExistenceChecksConfig,
+}
+
+impl ExistenceChecks {
+    pub fn new(lookup: Arc<dyn ExistenceLookup>, limiter: Arc<dyn RateLimitCounter>, config: ExistenceChecksConfig) -> Self {
+        Self { lookup, limiter, config }
+    }
+
+    /// Whether a user of the tenant has `email`; answers after
+    /// `email_response_ms` whatever the answer, and also when the lookup fails
+    pub async fn email_exists(&self, tenant: &TenantContext, email: &str) -> Result<bool> {
+        let answer_at = Instant::now() + Duration::from_millis(self.config.email_response_ms);
+        let exists = self.lookup.email_exists(tenant, email.trim()).await;
+        if Instant::now() > answer_at {
+            warn!(
+                "Email existence check took longer than {} ms, its response time is no longer uniform",
+                self.config.email_response_ms
+            );
+        }
+        tokio::time::sleep_until(answer_at).await;
+        exists
+    }
+
+    pub async fn sku_exists(&self, tenant_id: Uuid, sku: &str) -> Result<bool> {
+        self.lookup.sku_exists(tenant_id, sku.trim()).await
+    }
+
+    pub async fn customer_number_exists(&self, tenant_id: Uuid, customer_number: &str) -> Result<bool> {
+        self.lookup.customer_number_exists(tenant_id, customer_number.trim()).await
+    }
+
+    /// Standing of the user after this check, and whether it went over the limit
+    async fn count_check(&self, caller: &str) -> Option<(RateLimitStanding, bool)> {
+        let limit = self.config.requests_per_minute;
+        match self.limiter.hit(caller).await {
+            Ok((calls, reset_secs)) => Some((
+                RateLimitStanding::after(calls, limit, reset_secs),
+                RateLimitStanding::exceeded(calls, limit),
+            )),
+            Err(e) => {
+                warn!("Existence check rate limit counter unavailable, letting the check through: {}", e);
+                None
+            }
+        }
+    }
+
+    /// The answer to a SKU or customer number check, cacheable for `cache_secs`;
+    /// 304 when the client's copy holds the same answer
+    pub fn cached_answer(&self, exists: bool, request_headers: &HeaderMap) -> Response {
+        if self.config.cache_secs == 0 {
+            return answer(exists, "no-store".to_string(), None);
+        }
+        let etag = if exists { "\"exists\"" } else { "\"absent\"" };
+        let cache_control = format!("private, max-age={}", self.config.cache_secs);
+        let matches = request_headers
+            .get(header::IF_NONE_MATCH)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|tags| tags.split(',').any(|tag| tag.trim().trim_start_matches("W/") == etag || tag.trim() == "*"));
+        let mut response = answer(exists, cache_control, Some(etag));
+        if matches {
+            *response.status_mut() = StatusCode::NOT_MODIFIED;
+            *response.body_mut() = axum::body::Body::empty();
+            response.headers_mut().remove(header::CONTENT_TYPE);
+        }
+        response
+    }
+
+    /// The answer to an email check, which no cache may keep
+    pub fn uncached_answer(&self, exists: bool) -> Response {
+        answer(exists, "no-store".to_string(), None)
+    }
+}
+
+fn answer(exists: bool, cache_control: String, etag: Option<&'static str>) -> Response {
+    let mut response = Json(json!({ "success": true, "exists": exists })).into_response();
+    let headers = response.headers_mut();
+    headers.insert("x-exists", HeaderValue::from_static(if exists { "true" } else { "false" }));
+    if let Ok(value) = HeaderValue::from_str(&cache_control) {
+        headers.insert(header::CACHE_CONTROL, value);
+    }
+    headers.insert(header::VARY, HeaderValue::from_static("authorization, x-tenant-id"));
+    if let Some(etag) = etag {
+        headers.insert(header::ETAG, HeaderValue::from_static(etag));
+    }
+    response
+}
+
+/// Counts each check against the user's limit; runs after `auth_middleware`
+pub async fn existence_rate_limit_middleware(
+    State(checks): State<Arc<ExistenceChecks>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let caller = req.extensions().get::<RequestContext>().and_then(|context| {
+        let tenant = context.tenant_context.as_ref()?;
+        Some(format!("{}:{}", tenant.tenant_id.0, context.user_id?))
+    });
+    let Some(caller) = caller else {
+        return next.run(req).await;
+    };
+
+    let standing = checks.count_check(&caller).await;
+    let mut response = match standing {
+        Some((standing, true)) => rate_limited(&standing),
+        _ => next.run(req).await,
+    };
+    if let Some((standing, _)) = &standing {
+        set_headers(&mut response, standing);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, routing::get, Router};
+    use erp_core::TenantId;
+    use std::collections::{HashMap, HashSet};
+    use std::sync::Mutex;
+    use tower::ServiceExt;
+
+    /// Values taken per tenant; found emails take longer to look up than missing ones
+    #[derive(Default)]
+    struct MemoryLookup {
+        emails: HashSet<(Uuid, String)>,
+        skus: HashSet<(Uuid, String)>,
+        customer_numbers: HashSet<(Uuid, String)>,
+    }
+
+    #[async_trait]
+    impl ExistenceLookup for MemoryLookup {
+        async fn email_exists(&self, tenant: &TenantContext, email: &str) -> Result<bool> {
+            let found = self.emails.contains(&(tenant.tenant_id.0, email.to_string()));
+            if found {
+                tokio::time::sleep(Duration::from_millis(15)).await;
+            }
+            Ok(found)
+        }
+
+        async fn sku_exists(&self, tenant_id: Uuid, sku: &str) -> Result<bool> {
+            Ok(self.skus.contains(&(tenant_id, sku.to_string())))
+        }
+
+        async fn customer_number_exists(&self, tenant_id: Uuid, customer_number: &str) -> Result<bool> {
+            Ok(self.customer_numbers.contains(&(tenant_id, customer_number.to_string())))
+        }
+    }
+
+    /// Counts checks in memory; the window never ends
+    #[derive(Default)]
+    struct MemoryCounter {
+        calls: Mutex<HashMap<String, u64>>,
+    }
+
+    #[async_trait]
+    impl RateLimitCounter for MemoryCounter {
+        async fn hit(&self, caller: &str) -> Result<(u64, u64)> {
+            let mut calls = self.calls.lock().unwrap();
+            let count = calls.entry(caller.to_string()).or_default();
+            *count += 1;
+            Ok((*count, 30))
+        }
+    }
+
+    fn tenant(tenant_id: Uuid) -> TenantContext {
+        TenantContext { tenant_id: TenantId(tenant_id), schema_name: "tenant_test".to_string() }
+    }
+
+    fn checks(lookup: MemoryLookup, config: ExistenceChecksConfig) -> Arc<ExistenceChecks> {
+        Arc::new(ExistenceChecks::new(Arc::new(lookup), Arc::new(MemoryCounter::default()), config))
+    }
+
+    #[tokio::test]
+    async fn test_checks_answer_for_the_callers_tenant_only() {
+        let (ours, theirs) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut lookup = MemoryLookup::default();
+        lookup.emails.insert((ours, "anna@example.com".to_string()));
+        lookup.skus.insert((ours, "SKU-1".to_string()));
+        lookup.customer_numbers.insert((ours, "C000042".to_string()));
+        let config = ExistenceChecksConfig { email_response_ms: 0, ..Default::default() };
+        let checks = checks(lookup, config);
+
+        assert!(checks.email_exists(&tenant(ours), " anna@example.com ").await.unwrap());
+        assert!(!checks.email_exists(&tenant(ours), "bob@example.com").await.unwrap());
+        assert!(!checks.email_exists(&tenant(theirs), "anna@example.com").await.unwrap());
+        assert!(checks.sku_exists(ours, "SKU-1").await.unwrap());
+        assert!(!checks.sku_exists(theirs, "SKU-1").await.unwrap());
+        assert!(checks.customer_number_exists(ours, "C000042").await.unwrap());
+        assert!(!checks.customer_number_exists(ours, "C000043").await.unwrap());
+        assert!(!checks.customer_number_exists(theirs, "C000042").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_email_check_takes_as_long_found_or_not() {
+        let tenant_id = Uuid::new_v4();
+        let mut lookup = MemoryLookup::default();
+        lookup.emails.insert((tenant_id, "anna@example.com".to_string()));
+        let config = ExistenceChecksConfig { email_response_ms: 40, ..Default::default() };
+        let checks = checks(lookup, config);
+        let tenant = tenant(tenant_id);
+
+        let (mut found, mut missing) = (Vec::new(), Vec::new());
+        for _ in 0..15 {
+            for (email, samples) in [("anna@example.com", &mut found), ("nobody@example.com", &mut missing)] {
+                let started = std::time::Instant::now();
+                checks.email_exists(&tenant, email).await.unwrap();
+                samples.push(started.elapsed().as_secs_f64() * 1000.0);
+            }
+        }
+
+        let mean = |samples: &[f64]| samples.iter().sum::<f64>() / samples.len() as f64;
+        assert!(found.iter().chain(&missing).all(|ms| *ms >= 40.0), "every answer waits for the floor");
+        // The lookup alone differs by 15 ms; the answers may differ by scheduling noise only
+        let gap = (mean(&found) - mean(&missing)).abs();
+        assert!(gap < 5.0, "mean response times differ by {:.2} ms", gap);
+    }
+
+    #[test]
+    fn test_cached_and_uncached_answers() {
+        let checks = checks(MemoryLookup::default(), ExistenceChecksConfig::default());
+
+        let taken = checks.cached_answer(true, &HeaderMap::new());
+        assert_eq!(taken.status(), StatusCode::OK);
+        assert_eq!(taken.headers()[header::CACHE_CONTROL], "private, max-age=5");
+        assert_eq!(taken.headers()[header::ETAG], "\"exists\"");
+        assert_eq!(taken.headers()["x-exists"], "true");
+
+        let mut revalidation = HeaderMap::new();
+        revalidation.insert(header::IF_NONE_MATCH, HeaderValue::from_static("W/\"absent\""));
+        let unchanged = checks.cached_answer(false, &revalidation);
+        assert_eq!(unchanged.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(unchanged.headers()["x-exists"], "false");
+        assert_eq!(checks.cached_answer(true, &revalidation).status(), StatusCode::OK, "the answer changed");
+
+        let email = checks.uncached_answer(true);
+        assert_eq!(email.headers()[header::CACHE_CONTROL], "no-store");
+        assert!(email.headers().get(header::ETAG).is_none());
+    }
+
+    async fn call(checks: &Arc<ExistenceChecks>, context: &RequestContext) -> Response {
+        let app = Router::new()
+            .route("/products/exists", get(|| async { "answer" }))
+            .layer(axum::middleware::from_fn_with_state(checks.clone(), existence_rate_limit_middleware))
+            .layer(axum::Extension(context.clone()));
+        let request = Request::builder().uri("/products/exists?sku=SKU-1").body(Body::empty()).unwrap();
+        app.oneshot(request).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_checks_are_rate_limited_per_user() {
+        let config = ExistenceChecksConfig { requests_per_minute: 2, ..Default::default() };
+        let checks = checks(MemoryLookup::default(), config);
+        let tenant = tenant(Uuid::new_v4());
+        let anna = RequestContext::new().with_tenant_context(tenant.clone()).with_user_id(Uuid::new_v4());
+        let bob = RequestContext::new().with_tenant_context(tenant).with_user_id(Uuid::new_v4());
+
+        let first = call(&checks, &anna).await;
+        assert_eq!(first.status(), StatusCode::OK);
+        assert_eq!(first.headers()["x-ratelimit-limit"], "2");
+        assert_eq!(first.headers()["x-ratelimit-remaining"], "1");
+        assert_eq!(call(&checks, &anna).await.status(), StatusCode::OK);
+
+        let limited = call(&checks, &anna).await;
+        assert_eq!(limited.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(limited.headers()[header::RETRY_AFTER], "30");
+
+        // Another user of the tenant has a limit of their own
+        assert_eq!(call(&checks, &bob).await.status(), StatusCode::OK);
+    }
+}
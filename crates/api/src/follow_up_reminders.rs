@@ -0,0 +1,265 @@
+//! # Customer Communication Hooks
+//!
+//! Glue between the email service and the customer communication log:
+//!
+//! - [`CommunicationEmailLogger`] records every email sent with a customer
+//!   reference as a system-generated entry on that customer.
+//! - [`FollowUpReminderService`] emails each user a daily digest of their
+//!   overdue and soon-due follow-ups, honouring the `follow_up_reminder`
+//!   entry in `public.notification_preferences` (on unless opted out).
+
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use erp_auth::{
+    email::{SentEmail, SentEmailObserver},
+    AuthService, EmailService,
+};
+use erp_core::{FollowUpReminderConfig, TenantContext, TenantId};
+use erp_master_data::customer::{follow_up_digests, CommunicationRepository, CustomerCommunication, FollowUpDigest};
+use sqlx::{PgPool, Row};
+use std::sync::Arc;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// Notification type stored in `notification_preferences`
+pub const FOLLOW_UP_REMINDER: &str = "follow_up_reminder";
+
+/// Writes system-generated communication entries for customer emails
+pub struct CommunicationEmailLogger {
+    repository: Arc<dyn CommunicationRepository>,
+}
+
+impl CommunicationEmailLogger {
+    pub fn new(repository: Arc<dyn CommunicationRepository>) -> Self {
+        Self { repository }
+    }
+}
+
+#[async_trait]
+impl SentEmailObserver for CommunicationEmailLogger {
+    async fn email_sent(&self, email: &SentEmail) {
+        let Some(customer_id) = email.reference.customer_id else {
+            return;
+        };
+
+        let entry = CustomerCommunication::system_email(
+            email.reference.tenant_id,
+            customer_id,
+            &email.to,
+            &email.subject,
+            email.text_body.as_deref(),
+            email.reference.sent_by,
+            email.sent_at,
+        );
+        if let Err(e) = self.repository.insert_communication(&entry).await {
+            warn!("Failed to log email to customer {}: {}", customer_id, e);
+        }
+    }
+}
+
+/// Who gets reminded, and whether they want to be
+#[async_trait]
+pub trait ReminderDirectory: Send + Sync {
+    /// Email address for the reminder; `None` if the user opted out or is unknown
+    async fn reminder_address(&self, tenant_id: Uuid, user_id: Uuid) -> Result<Option<String>, String>;
+
+    async fn set_reminders_enabled(&self, tenant_id: Uuid, user_id: Uuid, enabled: bool) -> Result<(), String>;
+}
+
+/// Preferences from `public.notification_preferences`, addresses from the tenant's users
+pub struct PostgresReminderDirectory {
+    pool: PgPool,
+    auth_service: Arc<AuthService>,
+}
+
+impl PostgresReminderDirectory {
+    pub fn new(pool: PgPool, auth_service: Arc<AuthService>) -> Self {
+        Self { pool, auth_service }
+    }
+}
+
+#[async_trait]
+impl ReminderDirectory for PostgresReminderDirectory {
+    async fn reminder_address(&self, tenant_id: Uuid, user_id: Uuid) -> Result<Option<String>, String> {
+        let enabled: Option<bool> = sqlx::query(
+            "SELECT email_enabled FROM public.notification_preferences \
+             WHERE tenant_id = $1 AND user_id = $2 AND notification_type = $3",
+        )
+        .bind(tenant_id)
+        .bind(user_id)
+        .bind(FOLLOW_UP_REMINDER)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| e.to_string())?
+        .map(|row| row.get("email_enabled"));
+        if enabled == Some(false) {
+            return Ok(None);
+        }
+
+        user_email(&self.pool, &self.auth_service, tenant_id, user_id).await
+    }
+
+    async fn set_reminders_enabled(&self, tenant_id: Uuid, user_id: Uuid, enabled: bool) -> Result<(), String> {
+        sqlx::query(
+            "INSERT INTO public.notification_preferences (tenant_id, user_id, notification_type, email_enabled, updated_at) \
+             VALUES ($1, $2, $3, $4, NOW()) \
+             ON CONFLICT (tenant_id, user_id, notification_type) \
+             DO UPDATE SET email_enabled = EXCLUDED.email_enabled, updated_at = NOW()",
+        )
+        .bind(tenant_id)
+        .bind(user_id)
+        .bind(FOLLOW_UP_REMINDER)
+        .bind(enabled)
+        .execute(&self.pool)
+        .await
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+    }
+}
+
+/// Email address of a user of an active tenant; `None` if either is unknown
+pub(crate) async fn user_email(
+    pool: &PgPool,
+    auth_service: &AuthService,
+    tenant_id: Uuid,
+    user_id: Uuid,
+) -> Result<Option<String>, String> {
+    let schema_name: Option<String> = sqlx::query("SELECT schema_name FROM public.tenants WHERE id = $1 AND status = 'active'")
+        .bind(tenant_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| e.to_string())?
+        .map(|row| row.get("schema_name"));
+    let Some(schema_name) = schema_name else {
+        return Ok(None);
+    };
+
+    let tenant = TenantContext {
+        tenant_id: TenantId(tenant_id),
+        schema_name,
+    };
+    match auth_service.get_user(&tenant, user_id).await {
+        Ok(user) => Ok(Some(user.email)),
+        Err(e) if e.code == erp_core::ErrorCode::ResourceNotFound => Ok(None),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Daily follow-up reminder digests
+pub struct FollowUpReminderService {
+    communications: Arc<dyn CommunicationRepository>,
+    directory: Arc<dyn ReminderDirectory>,
+    email: Arc<EmailService>,
+    config: FollowUpReminderConfig,
+}
+
+impl FollowUpReminderService {
+    pub fn new(
+        communications: Arc<dyn CommunicationRepository>,
+        directory: Arc<dyn ReminderDirectory>,
+        email: Arc<EmailService>,
+        config: FollowUpReminderConfig,
+    ) -> Self {
+        Self {
+            communications,
+            directory,
+            email,
+            config,
+        }
+    }
+
+    pub fn directory(&self) -> &Arc<dyn ReminderDirectory> {
+        &self.directory
+    }
+
+    /// Send one digest per user with follow-ups due; returns the number of emails sent
+    pub async fn send_reminders(&self, now: DateTime<Utc>) -> Result<usize, String> {
+        let horizon = Duration::days(self.config.horizon_days.max(0));
+        let entries = self
+            .communications
+            .open_follow_ups_due(now + horizon)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let mut sent = 0;
+        for digest in follow_up_digests(entries, now, horizon) {
+            let address = match self.directory.reminder_address(digest.tenant_id, digest.user_id).await {
+                Ok(Some(address)) => address,
+                Ok(None) => continue,
+                Err(e) => {
+                    warn!("Skipping follow-up reminder for user {}: {}", digest.user_id, e);
+                    continue;
+                }
+            };
+
+            let (subject, text) = render_digest(&digest);
+            let html = format!("<pre>{}</pre>", html_escape(&text));
+            match self.email.send_email(&address, &subject, &html, Some(&text)).await {
+                Ok(()) => sent += 1,
+                Err(e) => warn!("Failed to send follow-up reminder to user {}: {}", digest.user_id, e),
+            }
+        }
+
+        Ok(sent)
+    }
+
+    /// Send reminders every `follow_up_reminders.interval_seconds` in the background
+    pub fn spawn(self: &Arc<Self>) -> Option<tokio::task::JoinHandle<()>> {
+        if !self.config.enabled {
+            return None;
+        }
+
+        let service = Arc::clone(self);
+        let period = std::time::Duration::from_secs(self.config.interval_seconds.max(3600));
+
+        Some(tokio::spawn(async move {
+            // First run one period after startup so restarts don't resend the digest
+            let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+            loop {
+                interval.tick().await;
+                match service.send_reminders(Utc::now()).await {
+                    Ok(0) => {}
+                    Ok(sent) => info!("Sent {} follow-up reminder emails", sent),
+                    Err(e) => warn!("Follow-up reminder run failed: {}", e),
+                }
+            }
+        }))
+    }
+}
+
+fn render_digest(digest: &FollowUpDigest) -> (String, String) {
+    let subject = match (digest.due.overdue.len(), digest.due.upcoming.len()) {
+        (0, upcoming) => format!("{} customer follow-up(s) due soon", upcoming),
+        (overdue, 0) => format!("{} overdue customer follow-up(s)", overdue),
+        (overdue, upcoming) => format!("{} overdue and {} upcoming customer follow-up(s)", overdue, upcoming),
+    };
+
+    let line = |entry: &CustomerCommunication| {
+        format!(
+            "- {} ({}, customer {}) due {}\n",
+            entry.subject,
+            entry.communication_type.as_str(),
+            entry.customer_id,
+            entry.follow_up_due.map(|d| d.format("%Y-%m-%d %H:%M UTC").to_string()).unwrap_or_default()
+        )
+    };
+
+    let mut text = String::new();
+    if !digest.due.overdue.is_empty() {
+        text.push_str("Overdue:\n");
+        digest.due.overdue.iter().for_each(|e| text.push_str(&line(e)));
+    }
+    if !digest.due.upcoming.is_empty() {
+        text.push_str("Coming up:\n");
+        digest.due.upcoming.iter().for_each(|e| text.push_str(&line(e)));
+    }
+
+    (subject, text)
+}
+
+pub(crate) fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,692 @@
+//! # Global Search
+//!
+//! One search box for the whole tenant under `GET /api/v1/search?q=&types=&limit=`.
+//! Each entity type is a [`SearchSource`]:
+//!
+//! - `customers`: the customer search of the caller's customer service, so a
+//!   portal account only finds the customers of its scope
+//! - `products`, `suppliers`, `locations`: name and number (SKU, supplier
+//!   number, location code) of the tenant's rows
+//!
+//! A type is searched only if the caller holds its read permission (see
+//! [`SearchType::permission`]); without it the type has no group in the
+//! answer, as if it did not exist.
+//!
+//! The sources are searched concurrently. A source that has not answered
+//! after `global_search.source_timeout_ms`, or failed, keeps its group with
+//! the results it has (none) and `truncated: true`; the other groups are
+//! answered without waiting for it, and no search takes longer than
+//! `global_search.timeout_ms`.
+//!
+//! Every result gets a [`relevance`] score between 0 and 1 from how well the
+//! query matches its name and number, the same way for every type, so scores
+//! compare across groups. Results are ordered by score within their group,
+//! groups by their best score.
+//!
+//! Summaries are built from names, numbers and statuses only; fields that
+//! are masked elsewhere (tax numbers, bank accounts, contact details, notes)
+//! are neither shown nor searched here.
+//!
+//! Complete answers are cached for `global_search.cache_ttl_ms` per tenant,
+//! query, limit and access (searchable types and portal scope), so the
+//! requests of repeated keystrokes are answered from memory.
+
+use async_trait::async_trait;
+use erp_core::{portal::PortalScope, Error, ErrorCode, GlobalSearchConfig, Result};
+use erp_master_data::customer::model::CustomerSearchCriteria;
+use erp_master_data::customer::service::CustomerService;
+use serde::Serialize;
+use sqlx::{PgPool, Row};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::time::{Duration, Instant};
+use tracing::warn;
+use uuid::Uuid;
+
+/// Longest query searched, in characters
+pub const MAX_QUERY_CHARS: usize = 100;
+
+/// What a global search looks through
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchType {
+    Customers,
+    Products,
+    Suppliers,
+    Locations,
+}
+
+impl SearchType {
+    pub const ALL: [SearchType; 4] = [
+        SearchType::Customers,
+        SearchType::Products,
+        SearchType::Suppliers,
+        SearchType::Locations,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SearchType::Customers => "customers",
+            SearchType::Products => "products",
+            SearchType::Suppliers => "suppliers",
+            SearchType::Locations => "locations",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "customers" => Some(SearchType::Customers),
+            "products" => Some(SearchType::Products),
+            "suppliers" => Some(SearchType::Suppliers),
+            "locations" => Some(SearchType::Locations),
+            _ => None,
+        }
+    }
+
+    /// Permission the caller needs for the type to be searched
+    pub fn permission(self) -> &'static str {
+        match self {
+            SearchType::Customers => "customers:read",
+            SearchType::Products => "products:read",
+            SearchType::Suppliers => "suppliers:read",
+            SearchType::Locations => "inventory:read",
+        }
+    }
+}
+
+/// What a result shows of the entity it found
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum SearchSummary {
+    Customer {
+        customer_number: String,
+        name: String,
+        status: String,
+    },
+    Product {
+        sku: String,
+        name: String,
+        status: String,
+    },
+    Supplier {
+        supplier_number: String,
+        name: String,
+        status: String,
+    },
+    Location {
+        code: Option<String>,
+        name: String,
+        location_type: String,
+    },
+}
+
+impl SearchSummary {
+    /// Name and number, with how much a match on each counts
+    fn searched_fields(&self) -> [(&str, f64); 2] {
+        match self {
+            SearchSummary::Customer { customer_number, name, .. } => [(name, 1.0), (customer_number, 0.9)],
+            SearchSummary::Product { sku, name, .. } => [(name, 1.0), (sku, 0.9)],
+            SearchSummary::Supplier { supplier_number, name, .. } => [(name, 1.0), (supplier_number, 0.9)],
+            SearchSummary::Location { code, name, .. } => [(name, 1.0), (code.as_deref().unwrap_or(""), 0.9)],
+        }
+    }
+}
+
+/// An entity a source found for the query
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchMatch {
+    pub id: Uuid,
+    pub summary: SearchSummary,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchHit {
+    pub id: Uuid,
+    /// Between 0 and 1, comparable across types
+    pub score: f64,
+    pub summary: SearchSummary,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchGroup {
+    #[serde(rename = "type")]
+    pub search_type: SearchType,
+    pub results: Vec<SearchHit>,
+    /// The source timed out or failed; its results may be incomplete
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchResults {
+    pub query: String,
+    pub groups: Vec<SearchGroup>,
+    /// Any group is truncated
+    pub truncated: bool,
+}
+
+/// Who searches: their tenant, permissions and portal scope
+#[derive(Debug, Clone)]
+pub struct SearchAccess {
+    pub tenant_id: Uuid,
+    pub permissions: Vec<String>,
+    /// The customers a portal account is bound to
+    pub portal: Option<PortalScope>,
+}
+
+impl SearchAccess {
+    pub fn may_search(&self, search_type: SearchType) -> bool {
+        self.permissions.iter().any(|permission| permission == search_type.permission())
+    }
+}
+
+/// One entity type's search, bound to the caller's tenant and scope
+#[async_trait]
+pub trait SearchSource: Send + Sync {
+    fn search_type(&self) -> SearchType;
+    /// Up to `limit` entities matching `query`, best matches first
+    async fn search(&self, query: &str, limit: usize) -> Result<Vec<SearchMatch>>;
+}
+
+/// How well `text` matches `query`, between 0 and 1: equal, prefix, word
+/// prefix, contained; longer texts score lower within each
+fn match_quality(query: &str, text: &str) -> f64 {
+    let text = text.to_lowercase();
+    if query.is_empty() || text.is_empty() {
+        return 0.0;
+    }
+    let coverage = (query.chars().count() as f64 / text.chars().count() as f64).min(1.0);
+    if text == query {
+        1.0
+    } else if text.starts_with(query) {
+        0.7 + 0.2 * coverage
+    } else if text.split(|c: char| !c.is_alphanumeric()).any(|word| word.starts_with(query)) {
+        0.5 + 0.2 * coverage
+    } else if text.contains(query) {
+        0.3 + 0.2 * coverage
+    } else {
+        0.0
+    }
+}
+
+/// Relevance of a result on the common 0..1 scale: the best match of the
+/// query on its name or number. Results found through other fields score
+/// just above 0.
+pub fn relevance(query: &str, summary: &SearchSummary) -> f64 {
+    let query = query.trim().to_lowercase();
+    let best = summary
+        .searched_fields()
+        .iter()
+        .map(|(text, weight)| match_quality(&query, text) * weight)
+        .fold(0.0, f64::max);
+    (best.max(0.05) * 1000.0).round() / 1000.0
+}
+
+/// The results of one source, best first
+fn group(search_type: SearchType, query: &str, matches: Vec<SearchMatch>, limit: usize, truncated: bool) -> SearchGroup {
+    let mut results: Vec<SearchHit> = matches
+        .into_iter()
+        .map(|found| SearchHit {
+            id: found.id,
+            score: relevance(query, &found.summary),
+            summary: found.summary,
+        })
+        .collect();
+    results.sort_by(|a, b| b.score.total_cmp(&a.score));
+    results.truncate(limit);
+    SearchGroup {
+        search_type,
+        results,
+        truncated,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CacheKey {
+    tenant_id: Uuid,
+    query: String,
+    types: Vec<SearchType>,
+    limit: usize,
+    /// Portal customer and whether its children are included
+    portal: Option<(Uuid, bool)>,
+}
+
+pub struct GlobalSearch {
+    config: GlobalSearchConfig,
+    cache: Mutex<HashMap<CacheKey, (Instant, SearchResults)>>,
+}
+
+impl GlobalSearch {
+    pub fn new(config: GlobalSearchConfig) -> Self {
+        Self {
+            config,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Results per group for a requested limit
+    pub fn limit(&self, requested: Option<usize>) -> usize {
+        requested.unwrap_or(self.config.default_limit).clamp(1, self.config.max_limit.max(1))
+    }
+
+    /// Search `types` (all if empty) among `sources`, leaving out the types
+    /// `access` may not read
+    pub async fn search(
+        &self,
+        access: &SearchAccess,
+        query: &str,
+        types: &[SearchType],
+        limit: usize,
+        sources: Vec<Arc<dyn SearchSource>>,
+    ) -> SearchResults {
+        let query = query.trim().to_string();
+        let searched: Vec<SearchType> = SearchType::ALL
+            .into_iter()
+            .filter(|search_type| types.is_empty() || types.contains(search_type))
+            .filter(|search_type| access.may_search(*search_type))
+            .collect();
+        let key = CacheKey {
+            tenant_id: access.tenant_id,
+            query: query.to_lowercase(),
+            types: searched.clone(),
+            limit,
+            portal: access.portal.map(|scope| (scope.customer_id, scope.include_children)),
+        };
+        if let Some(results) = self.cached(&key) {
+            return SearchResults { query, ..results };
+        }
+
+        let started = Instant::now();
+        let deadline = started
+            + Duration::from_millis(self.config.source_timeout_ms.min(self.config.timeout_ms));
+        let searches: Vec<_> = sources
+            .into_iter()
+            .filter(|source| searched.contains(&source.search_type()))
+            .map(|source| {
+                let query = query.clone();
+                let search_type = source.search_type();
+                let task = tokio::spawn(async move {
+                    tokio::time::timeout_at(deadline, source.search(&query, limit)).await
+                });
+                (search_type, task)
+            })
+            .collect();
+
+        let mut groups = Vec::new();
+        for (search_type, task) in searches {
+            let (matches, truncated) = match task.await {
+                Ok(Ok(Ok(matches))) => (matches, false),
+                Ok(Ok(Err(e))) => {
+                    warn!("Global search of {} failed: {}", search_type.as_str(), e);
+                    (Vec::new(), true)
+                }
+                Ok(Err(_)) => {
+                    warn!(
+                        "Global search of {} took longer than {} ms, answered without it",
+                        search_type.as_str(),
+                        (deadline - started).as_millis()
+                    );
+                    (Vec::new(), true)
+                }
+                Err(e) => {
+                    warn!("Global search of {} panicked: {}", search_type.as_str(), e);
+                    (Vec::new(), true)
+                }
+            };
+            groups.push(group(search_type, &query, matches, limit, truncated));
+        }
+        let best = |group: &SearchGroup| group.results.first().map_or(0.0, |hit| hit.score);
+        groups.sort_by(|a, b| best(b).total_cmp(&best(a)));
+
+        let truncated = groups.iter().any(|group| group.truncated);
+        let results = SearchResults { query, groups, truncated };
+        if !truncated {
+            self.remember(key, results.clone());
+        }
+        results
+    }
+
+    fn cached(&self, key: &CacheKey) -> Option<SearchResults> {
+        let cache = self.cache.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let (stored_at, results) = cache.get(key)?;
+        (stored_at.elapsed() < Duration::from_millis(self.config.cache_ttl_ms)).then(|| results.clone())
+    }
+
+    fn remember(&self, key: CacheKey, results: SearchResults) {
+        if self.config.cache_ttl_ms == 0 || self.config.cache_entries == 0 {
+            return;
+        }
+        let ttl = Duration::from_millis(self.config.cache_ttl_ms);
+        let mut cache = self.cache.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if cache.len() >= self.config.cache_entries {
+            cache.retain(|_, (stored_at, _)| stored_at.elapsed() < ttl);
+        }
+        if cache.len() >= self.config.cache_entries {
+            if let Some(oldest) = cache.iter().min_by_key(|(_, (stored_at, _))| *stored_at).map(|(key, _)| key.clone()) {
+                cache.remove(&oldest);
+            }
+        }
+        cache.insert(key, (Instant::now(), results));
+    }
+}
+
+/// Customers through the caller's customer service, portal scope included
+pub struct CustomerSearchSource {
+    service: Box<dyn CustomerService>,
+}
+
+impl CustomerSearchSource {
+    pub fn new(service: Box<dyn CustomerService>) -> Self {
+        Self { service }
+    }
+}
+
+#[async_trait]
+impl SearchSource for CustomerSearchSource {
+    fn search_type(&self) -> SearchType {
+        SearchType::Customers
+    }
+
+    async fn search(&self, query: &str, limit: usize) -> Result<Vec<SearchMatch>> {
+        let criteria = CustomerSearchCriteria {
+            search_term: Some(query.to_string()),
+            page: Some(1),
+            page_size: Some(limit as u32),
+            ..Default::default()
+        };
+        let found = self
+            .service
+            .search_customers(criteria)
+            .await
+            .map_err(|e| Error::new(ErrorCode::InternalServerError, e.to_string()))?;
+        Ok(found
+            .customers
+            .into_iter()
+            .map(|customer| SearchMatch {
+                id: customer.id,
+                summary: SearchSummary::Customer {
+                    customer_number: customer.customer_number,
+                    name: customer.legal_name,
+                    status: format!("{:?}", customer.status).to_lowercase(),
+                },
+            })
+            .collect())
+    }
+}
+
+/// `%`, `_` and `\` match literally
+fn like_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
+
+/// Products, suppliers or locations of one tenant by name and number
+pub struct PostgresSearchSource {
+    pool: PgPool,
+    tenant_id: Uuid,
+    search_type: SearchType,
+}
+
+impl PostgresSearchSource {
+    pub fn new(pool: PgPool, tenant_id: Uuid, search_type: SearchType) -> Self {
+        Self { pool, tenant_id, search_type }
+    }
+
+    /// Matches on name or number, exact and prefix matches first so the
+    /// limit does not cut them off; `IS TRUE` keeps rows without trade name
+    /// or code from sorting first
+    fn statement(&self) -> Option<&'static str> {
+        match self.search_type {
+            SearchType::Products => Some(
+                "SELECT id, sku AS number, name, status::text AS status FROM products \
+                 WHERE tenant_id = $1 AND (name ILIKE $2 OR sku ILIKE $2) \
+                 ORDER BY (lower(name) = $4 OR lower(sku) = $4) DESC, (name ILIKE $3 OR sku ILIKE $3) DESC, name \
+                 LIMIT $5",
+            ),
+            SearchType::Suppliers => Some(
+                "SELECT id, supplier_number AS number, COALESCE(trade_name, legal_name) AS name, status::text AS status \
+                 FROM suppliers \
+                 WHERE tenant_id = $1 AND (legal_name ILIKE $2 OR trade_name ILIKE $2 OR supplier_number ILIKE $2) \
+                 ORDER BY (lower(legal_name) = $4 OR lower(trade_name) = $4 OR lower(supplier_number) = $4) IS TRUE DESC, \
+                          (legal_name ILIKE $3 OR trade_name ILIKE $3 OR supplier_number ILIKE $3) IS TRUE DESC, legal_name \
+                 LIMIT $5",
+            ),
+            SearchType::Locations => Some(
+                "SELECT id, code AS number, name, location_type AS status FROM locations \
+                 WHERE tenant_id = $1 AND is_active AND (name ILIKE $2 OR code ILIKE $2) \
+                 ORDER BY (lower(name) = $4 OR lower(code) = $4) IS TRUE DESC, (name ILIKE $3 OR code ILIKE $3) IS TRUE DESC, name \
+                 LIMIT $5",
+            ),
+            SearchType::Customers => None,
+        }
+    }
+}
+
+#[async_trait]
+impl SearchSource for PostgresSearchSource {
+    fn search_type(&self) -> SearchType {
+        self.search_type
+    }
+
+    async fn search(&self, query: &str, limit: usize) -> Result<Vec<SearchMatch>> {
+        let Some(statement) = self.statement() else {
+            return Ok(Vec::new());
+        };
+        let escaped = like_escape(query);
+        let rows = sqlx::query(statement)
+            .bind(self.tenant_id)
+            .bind(format!("%{}%", escaped))
+            .bind(format!("{}%", escaped))
+            .bind(query.to_lowercase())
+            .bind(limit as i64)
+            .fetch_all(&self.pool)
+            .await?;
+
+        let matches = rows
+            .iter()
+            .map(|row| -> std::result::Result<SearchMatch, sqlx::Error> {
+                let number: Option<String> = row.try_get("number")?;
+                let name: String = row.try_get("name")?;
+                let status: Option<String> = row.try_get("status")?;
+                let status = status.unwrap_or_default();
+                let summary = match self.search_type {
+                    SearchType::Products => SearchSummary::Product { sku: number.unwrap_or_default(), name, status },
+                    SearchType::Suppliers => SearchSummary::Supplier {
+                        supplier_number: number.unwrap_or_default(),
+                        name,
+                        status,
+                    },
+                    _ => SearchSummary::Location { code: number, name, location_type: status },
+                };
+                Ok(SearchMatch { id: row.try_get("id")?, summary })
+            })
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(matches)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct StaticSource {
+        search_type: SearchType,
+        matches: Vec<SearchMatch>,
+        delay: Duration,
+        calls: AtomicUsize,
+    }
+
+    impl StaticSource {
+        fn new(search_type: SearchType, matches: Vec<SearchSummary>) -> Arc<Self> {
+            Arc::new(Self {
+                search_type,
+                matches: matches
+                    .into_iter()
+                    .map(|summary| SearchMatch { id: Uuid::new_v4(), summary })
+                    .collect(),
+                delay: Duration::ZERO,
+                calls: AtomicUsize::new(0),
+            })
+        }
+
+        fn slow(search_type: SearchType, delay: Duration) -> Arc<Self> {
+            Arc::new(Self {
+                search_type,
+                matches: Vec::new(),
+                delay,
+                calls: AtomicUsize::new(0),
+            })
+        }
+    }
+
+    #[async_trait]
+    impl SearchSource for StaticSource {
+        fn search_type(&self) -> SearchType {
+            self.search_type
+        }
+
+        async fn search(&self, _query: &str, limit: usize) -> Result<Vec<SearchMatch>> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(self.delay).await;
+            Ok(self.matches.iter().take(limit).cloned().collect())
+        }
+    }
+
+    fn customer(name: &str) -> SearchSummary {
+        SearchSummary::Customer {
+            customer_number: "C-000042".to_string(),
+            name: name.to_string(),
+            status: "active".to_string(),
+        }
+    }
+
+    fn product(sku: &str, name: &str) -> SearchSummary {
+        SearchSummary::Product {
+            sku: sku.to_string(),
+            name: name.to_string(),
+            status: "active".to_string(),
+        }
+    }
+
+    fn supplier(name: &str) -> SearchSummary {
+        SearchSummary::Supplier {
+            supplier_number: "S-7".to_string(),
+            name: name.to_string(),
+            status: "active".to_string(),
+        }
+    }
+
+    fn access(permissions: &[&str]) -> SearchAccess {
+        SearchAccess {
+            tenant_id: Uuid::new_v4(),
+            permissions: permissions.iter().map(|p| p.to_string()).collect(),
+            portal: None,
+        }
+    }
+
+    fn sources() -> Vec<Arc<dyn SearchSource>> {
+        vec![
+            StaticSource::new(SearchType::Customers, vec![customer("Acme GmbH")]),
+            StaticSource::new(SearchType::Products, vec![product("ACME-1", "Anvil")]),
+            StaticSource::new(SearchType::Suppliers, vec![supplier("Acme Supplies")]),
+        ]
+    }
+
+    fn types(results: &SearchResults) -> Vec<SearchType> {
+        results.groups.iter().map(|group| group.search_type).collect()
+    }
+
+    #[tokio::test]
+    async fn test_types_the_caller_may_not_read_have_no_group() {
+        let search = GlobalSearch::new(GlobalSearchConfig::default());
+
+        let results = search
+            .search(&access(&["customers:read", "suppliers:read"]), "acme", &[], 5, sources())
+            .await;
+        assert!(!types(&results).contains(&SearchType::Products));
+        assert_eq!(results.groups.len(), 2);
+
+        let results = search
+            .search(&access(&["customers:read", "suppliers:read"]), "acme", &[SearchType::Products], 5, sources())
+            .await;
+        assert!(results.groups.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_slow_source_is_answered_without_and_flagged_truncated() {
+        let config = GlobalSearchConfig {
+            timeout_ms: 300,
+            source_timeout_ms: 50,
+            ..Default::default()
+        };
+        let search = GlobalSearch::new(config);
+        let slow = StaticSource::slow(SearchType::Products, Duration::from_secs(5));
+        let sources: Vec<Arc<dyn SearchSource>> =
+            vec![StaticSource::new(SearchType::Customers, vec![customer("Acme GmbH")]), slow.clone()];
+        let access = access(&["customers:read", "products:read"]);
+
+        let started = std::time::Instant::now();
+        let results = search.search(&access, "acme", &[], 5, sources.clone()).await;
+        assert!(started.elapsed() < std::time::Duration::from_millis(300));
+
+        assert!(results.truncated);
+        let customers = results.groups.iter().find(|g| g.search_type == SearchType::Customers).unwrap();
+        assert!(!customers.truncated);
+        assert_eq!(customers.results.len(), 1);
+        let products = results.groups.iter().find(|g| g.search_type == SearchType::Products).unwrap();
+        assert!(products.truncated && products.results.is_empty());
+
+        // A truncated answer is not cached
+        search.search(&access, "acme", &[], 5, sources).await;
+        assert_eq!(slow.calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_scores_order_results_and_groups_across_types() {
+        let search = GlobalSearch::new(GlobalSearchConfig::default());
+        let sources: Vec<Arc<dyn SearchSource>> = vec![
+            StaticSource::new(SearchType::Customers, vec![customer("The Best Acme Traders")]),
+            StaticSource::new(SearchType::Products, vec![product("X-1", "Hammer"), product("ACME", "Anvil")]),
+            StaticSource::new(SearchType::Suppliers, vec![supplier("Acme Supplies International")]),
+        ];
+        let access = access(&["customers:read", "products:read", "suppliers:read"]);
+
+        let results = search.search(&access, "Acme", &[], 5, sources).await;
+
+        // Exact SKU, name prefix, word prefix
+        assert_eq!(types(&results), vec![SearchType::Products, SearchType::Suppliers, SearchType::Customers]);
+        let scores: Vec<f64> = results.groups.iter().map(|group| group.results[0].score).collect();
+        assert!(scores.windows(2).all(|pair| pair[0] > pair[1]));
+        assert!(scores.iter().all(|score| (0.0..=1.0).contains(score)));
+
+        let products = &results.groups[0].results;
+        assert_eq!(products[0].score, 0.9);
+        assert_eq!(products[1].score, 0.05);
+        assert_eq!(relevance("acme", &customer("Acme")), 1.0);
+    }
+
+    #[tokio::test]
+    async fn test_repeated_queries_are_answered_from_the_cache() {
+        let search = GlobalSearch::new(GlobalSearchConfig::default());
+        let customers = StaticSource::new(SearchType::Customers, vec![customer("Acme GmbH")]);
+        let sources: Vec<Arc<dyn SearchSource>> = vec![customers.clone()];
+        let access = access(&["customers:read"]);
+
+        search.search(&access, "acme", &[], 5, sources.clone()).await;
+        let again = search.search(&access, " ACME ", &[], 5, sources.clone()).await;
+        assert_eq!(customers.calls.load(Ordering::SeqCst), 1);
+        assert_eq!(again.query, "ACME");
+        assert_eq!(again.groups[0].results.len(), 1);
+
+        // Other tenants and other access do not share the answer
+        let mut other = access.clone();
+        other.tenant_id = Uuid::new_v4();
+        search.search(&other, "acme", &[], 5, sources.clone()).await;
+        let mut portal = access.clone();
+        portal.portal = Some(PortalScope {
+            customer_id: Uuid::new_v4(),
+            include_children: false,
+        });
+        search.search(&portal, "acme", &[], 5, sources).await;
+        assert_eq!(customers.calls.load(Ordering::SeqCst), 3);
+    }
+}
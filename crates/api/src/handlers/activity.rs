@@ -0,0 +1,140 @@
+//! Tenant activity feed handlers
+//!
+//! Audit, customer and inventory events of the signed-in user's tenant in one
+//! feed, newest first, and the same entries as a CSV audit export under the
+//! tenant's letterhead. See [`crate::activity`] for how entries are projected.
+
+use axum::{
+    extract::{Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Json, Response},
+    routing::{get, Router},
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::api_middleware::tenant_context::TenantUser;
+use crate::{
+    activity::{export_csv, ActivityCursor, ActivityQuery, EXPORT_MAX_ROWS, MASK},
+    letterhead::export_header,
+    state::AppState,
+};
+use erp_core::{warnings, Warning, WarningCode};
+
+#[derive(Debug, Deserialize)]
+pub struct ActivityParams {
+    /// Comma-separated object types, e.g. `customer,product`
+    pub types: Option<String>,
+    pub actor: Option<Uuid>,
+    pub since: Option<DateTime<Utc>>,
+    /// Free text matched against the summary
+    pub q: Option<String>,
+    /// `next_cursor` of the previous page
+    pub cursor: Option<String>,
+    pub limit: Option<i64>,
+}
+
+/// Create activity feed routes; they need an authenticated user
+pub fn activity_routes() -> Router<AppState> {
+    Router::new().route("/", get(activity_feed))
+}
+
+/// Create the activity export route; it belongs in the exports concurrency group
+pub fn activity_export_routes() -> Router<AppState> {
+    Router::new().route("/export", get(activity_export))
+}
+
+fn activity_query(params: &ActivityParams) -> Result<ActivityQuery, StatusCode> {
+    let cursor = match params.cursor.as_deref() {
+        Some(cursor) => Some(ActivityCursor::decode(cursor).ok_or(StatusCode::BAD_REQUEST)?),
+        None => None,
+    };
+    Ok(ActivityQuery {
+        object_types: params
+            .types
+            .as_deref()
+            .map(|types| {
+                types
+                    .split(',')
+                    .map(|t| t.trim().to_lowercase())
+                    .filter(|t| !t.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default(),
+        actor_id: params.actor,
+        since: params.since,
+        text: params.q.as_deref().map(str::trim).filter(|q| !q.is_empty()).map(str::to_string),
+        cursor,
+    })
+}
+
+/// One page of the tenant's activity feed
+async fn activity_feed(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, .. }: TenantUser,
+    Query(params): Query<ActivityParams>,
+) -> Result<Json<Value>, StatusCode> {
+    let query = activity_query(&params)?;
+
+    match state.activity.feed(tenant_context.tenant_id.0, &query, params.limit).await {
+        Ok(page) => Ok(Json(json!({
+            "success": true,
+            "entries": page.entries,
+            "next_cursor": page.next_cursor,
+            "has_more": page.has_more
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to load activity feed of tenant {}: {}", tenant_context.tenant_id.0, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Every matching entry, up to the export limit, as CSV below the export header
+async fn activity_export(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+    Query(params): Query<ActivityParams>,
+) -> Result<Response, StatusCode> {
+    let tenant_id = tenant_context.tenant_id.0;
+    let query = activity_query(&params)?;
+
+    let (entries, truncated) = state.activity.export(tenant_id, &query).await.map_err(|e| {
+        tracing::error!("Failed to export activity of tenant {}: {}", tenant_id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    let masked = entries.iter().filter(|entry| entry.summary.contains(MASK)).count();
+    if masked > 0 {
+        warnings::warn(
+            Warning::new(
+                WarningCode::MaskedFieldsOmitted,
+                format!("Tax numbers were masked in the summaries of {} entries", masked),
+            )
+            .with_field("summary"),
+        );
+    }
+    let export = export_header(state.letterheads.as_ref(), tenant_id, "Audit trail", &request_context)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to load letterhead of tenant {}: {}", tenant_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .with_optional_filter("types", params.types.as_deref())
+        .with_optional_filter("actor", params.actor)
+        .with_optional_filter("since", params.since.map(|since| since.to_rfc3339()))
+        .with_optional_filter("q", params.q.as_deref())
+        .with_optional_filter("cursor", params.cursor.as_deref())
+        .with_optional_filter("truncated_at", truncated.then_some(EXPORT_MAX_ROWS));
+
+    let filename = format!("audit-trail-{}-{}.csv", tenant_id, export.generated_at.format("%Y%m%d%H%M%S"));
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename)),
+        ],
+        export_csv(&export, &entries),
+    )
+        .into_response())
+}
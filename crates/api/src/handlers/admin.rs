@@ -0,0 +1,364 @@
+//! Platform administration handlers
+//!
+//! Cross-tenant operational endpoints. All routes require the `system:admin` permission.
+//! Permission usage reports support least-privilege reviews of a tenant's roles.
+//! The migration history tells which build applied each schema migration and how long it ran.
+//! The effective configuration is what the server started with, secrets as fingerprints.
+//! The nil actor report counts rows written before writes had to name their actor.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Json, Response},
+    routing::{get, Router},
+};
+use chrono::{Duration, NaiveDate, Utc};
+use erp_core::{actor, effective_config, migration_audit};
+use erp_core::permission_usage::PermissionUsageReport;
+use erp_core::retention::{RetentionAction, RetentionPolicy};
+use erp_core::{Error, ErrorCode, RequestContext};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::{letterhead::export_header, state::AppState};
+
+#[derive(Debug, Deserialize)]
+pub struct TenantHealthParams {
+    /// Bypass the result cache
+    #[serde(default)]
+    pub refresh: bool,
+}
+
+/// Create admin routes
+pub fn admin_routes() -> Router<AppState> {
+    Router::new()
+        .route("/tenants/health", get(tenants_health))
+        .route("/tenants/:id/health", get(tenant_health))
+        .route("/retention/policies", get(retention_policies).put(update_retention_policy))
+        .route("/retention/tenants/:id/policies", get(tenant_retention_policies))
+        .route("/retention/tenants/:id/policies/:category/dry-run", get(retention_dry_run))
+        .route("/retention/log", get(retention_log))
+        .route("/permissions/usage", get(permission_usage))
+        .route("/migrations", get(migrations))
+        .route("/config/effective", get(effective_configuration))
+        .route("/actors/nil-report", get(nil_actor_report))
+}
+
+/// Create the permission usage export route; it belongs in the exports concurrency group
+pub fn admin_export_routes() -> Router<AppState> {
+    Router::new().route("/permissions/usage/export", get(permission_usage_export))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RetentionPolicyRequest {
+    /// Omit for the global default
+    pub tenant_id: Option<Uuid>,
+    pub category: String,
+    pub retention_days: i64,
+    pub action: RetentionAction,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RetentionLogParams {
+    pub tenant_id: Option<Uuid>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MigrationHistoryParams {
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PermissionUsageParams {
+    /// Defaults to the caller's tenant
+    pub tenant_id: Option<Uuid>,
+    /// Only this role (by name)
+    pub role: Option<String>,
+    /// Start of the window; defaults to `permission_usage.default_window_days` ago
+    pub since: Option<NaiveDate>,
+}
+
+fn error_status(e: &Error) -> StatusCode {
+    match e.code {
+        ErrorCode::NotFound | ErrorCode::ResourceNotFound => StatusCode::NOT_FOUND,
+        ErrorCode::ValidationFailed => StatusCode::BAD_REQUEST,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// Health and migration status of every active tenant schema
+async fn tenants_health(
+    State(state): State<AppState>,
+    Query(params): Query<TenantHealthParams>,
+) -> Result<Json<Value>, StatusCode> {
+    match state.tenant_health.check_all(params.refresh).await {
+        Ok(summary) => Ok(Json(json!({
+            "success": true,
+            "summary": summary
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to check tenant health: {}", e);
+            Err(StatusCode::SERVICE_UNAVAILABLE)
+        }
+    }
+}
+
+/// Fresh health check of a single tenant, including per-check latency
+async fn tenant_health(
+    State(state): State<AppState>,
+    Path(tenant_id): Path<Uuid>,
+) -> Result<Json<Value>, StatusCode> {
+    match state.tenant_health.check_one(tenant_id).await {
+        Ok(Some(report)) => Ok(Json(json!({
+            "success": true,
+            "tenant": report
+        }))),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            tracing::error!("Failed to check health of tenant {}: {}", tenant_id, e);
+            Err(StatusCode::SERVICE_UNAVAILABLE)
+        }
+    }
+}
+
+/// Registered categories with their defaults and legal minimums, plus the global policies
+async fn retention_policies(State(state): State<AppState>) -> Result<Json<Value>, StatusCode> {
+    let retention = &state.retention;
+    let categories: Vec<Value> = retention
+        .registry()
+        .categories()
+        .map(|c| json!({
+            "name": c.name,
+            "description": c.description,
+            "default_days": c.default_days,
+            "default_action": c.default_action,
+            "supported_actions": c.supported_actions,
+            "legal_minimum_days": retention.config().legal_minimum_days.get(&c.name)
+        }))
+        .collect();
+
+    match retention.store().policies(None).await {
+        Ok(policies) => Ok(Json(json!({
+            "success": true,
+            "categories": categories,
+            "global_policies": policies
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to load retention policies: {}", e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Set a tenant or global policy; periods below the legal minimum are rejected with 400
+async fn update_retention_policy(
+    State(state): State<AppState>,
+    request_context: RequestContext,
+    Json(request): Json<RetentionPolicyRequest>,
+) -> Result<(StatusCode, Json<Value>), StatusCode> {
+    let policy = RetentionPolicy {
+        tenant_id: request.tenant_id,
+        category: request.category,
+        retention_days: request.retention_days,
+        action: request.action,
+        updated_by: request_context.user_id,
+        updated_at: Utc::now(),
+    };
+
+    match state.retention.update_policy(policy).await {
+        Ok(policy) => Ok((StatusCode::OK, Json(json!({
+            "success": true,
+            "policy": policy
+        })))),
+        Err(e) if e.code == ErrorCode::ValidationFailed => Ok((StatusCode::BAD_REQUEST, Json(json!({
+            "success": false,
+            "error": e.message
+        })))),
+        Err(e) => {
+            tracing::error!("Failed to update retention policy: {}", e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Effective policy of every category for a tenant and where it comes from
+async fn tenant_retention_policies(
+    State(state): State<AppState>,
+    Path(tenant_id): Path<Uuid>,
+) -> Result<Json<Value>, StatusCode> {
+    match state.retention.effective_policies(tenant_id).await {
+        Ok(policies) => Ok(Json(json!({
+            "success": true,
+            "tenant_id": tenant_id,
+            "policies": policies
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to resolve retention policies of tenant {}: {}", tenant_id, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Rows the tenant's policy for a category would affect if it ran now
+async fn retention_dry_run(
+    State(state): State<AppState>,
+    Path((tenant_id, category)): Path<(Uuid, String)>,
+) -> Result<Json<Value>, StatusCode> {
+    match state.retention.dry_run(tenant_id, &category, Utc::now()).await {
+        Ok(dry_run) => Ok(Json(json!({
+            "success": true,
+            "dry_run": dry_run
+        }))),
+        Err(e) => {
+            tracing::error!("Retention dry run of {} for tenant {} failed: {}", category, tenant_id, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Recent enforcement runs, newest first
+async fn retention_log(
+    State(state): State<AppState>,
+    Query(params): Query<RetentionLogParams>,
+) -> Result<Json<Value>, StatusCode> {
+    let limit = params.limit.unwrap_or(100).clamp(1, 1000);
+
+    match state.retention.store().enforcement_log(params.tenant_id, limit).await {
+        Ok(entries) => Ok(Json(json!({
+            "success": true,
+            "count": entries.len(),
+            "entries": entries
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to load retention enforcement log: {}", e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Audited migration runs, newest first; those slower than `migration_audit.slow_threshold_secs` are marked `slow`
+async fn migrations(
+    State(state): State<AppState>,
+    Query(params): Query<MigrationHistoryParams>,
+) -> Result<Json<Value>, StatusCode> {
+    let limit = params.limit.unwrap_or(100).clamp(1, 1000);
+    let threshold = std::time::Duration::from_secs(state.config.migration_audit.slow_threshold_secs);
+
+    match migration_audit::history(&state.db.main_pool, limit).await {
+        Ok(records) => {
+            let migrations: Vec<Value> = records
+                .iter()
+                .map(|record| {
+                    let mut entry = json!(record);
+                    entry["slow"] = json!(record.is_slow(threshold));
+                    entry
+                })
+                .collect();
+            Ok(Json(json!({
+                "success": true,
+                "count": migrations.len(),
+                "slow_threshold_secs": threshold.as_secs(),
+                "migrations": migrations
+            })))
+        }
+        Err(e) => {
+            tracing::error!("Failed to load migration history: {}", e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Every setting the server started with, its value and the file or
+/// environment variable it came from
+async fn effective_configuration() -> Result<Json<Value>, StatusCode> {
+    let snapshot = effective_config::current().ok_or(StatusCode::NOT_FOUND)?;
+    Ok(Json(json!({
+        "success": true,
+        "effective_config": snapshot
+    })))
+}
+
+/// Rows recorded as made by the nil UUID, per schema, table and actor column
+async fn nil_actor_report(State(state): State<AppState>) -> Result<Json<Value>, StatusCode> {
+    match actor::nil_actor_report(&state.db.main_pool).await {
+        Ok(report) => {
+            let total: i64 = report.iter().map(|count| count.rows).sum();
+            Ok(Json(json!({
+                "success": true,
+                "total_rows": total,
+                "columns": report
+            })))
+        }
+        Err(e) => {
+            tracing::error!("Failed to build the nil actor report: {}", e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+async fn load_permission_usage(
+    state: &AppState,
+    request_context: &RequestContext,
+    params: &PermissionUsageParams,
+) -> Result<PermissionUsageReport, StatusCode> {
+    let tenant_id = params
+        .tenant_id
+        .or_else(|| request_context.tenant_context.as_ref().map(|t| t.tenant_id.0))
+        .ok_or(StatusCode::BAD_REQUEST)?;
+    let since = params.since.unwrap_or_else(|| {
+        Utc::now().date_naive() - Duration::days(state.permission_usage.config().default_window_days)
+    });
+
+    state
+        .permission_usage
+        .report(tenant_id, params.role.as_deref(), since)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to build permission usage report for tenant {}: {}", tenant_id, e);
+            error_status(&e)
+        })
+}
+
+/// Permissions each role used in the window, with those held but never used
+/// and those only used under an admin override highlighted
+async fn permission_usage(
+    State(state): State<AppState>,
+    Query(params): Query<PermissionUsageParams>,
+    request_context: RequestContext,
+) -> Result<Json<Value>, StatusCode> {
+    let report = load_permission_usage(&state, &request_context, &params).await?;
+    Ok(Json(json!({
+        "success": true,
+        "report": report
+    })))
+}
+
+/// The permission usage report as CSV below the export header, one line per
+/// role and permission
+async fn permission_usage_export(
+    State(state): State<AppState>,
+    Query(params): Query<PermissionUsageParams>,
+    request_context: RequestContext,
+) -> Result<Response, StatusCode> {
+    let report = load_permission_usage(&state, &request_context, &params).await?;
+    let export = export_header(state.letterheads.as_ref(), report.tenant_id, "Permission usage", &request_context)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to load letterhead of tenant {}: {}", report.tenant_id, e);
+            error_status(&e)
+        })?
+        .with_filter("tenant_id", report.tenant_id)
+        .with_optional_filter("role", params.role.as_deref())
+        .with_filter("since", report.since);
+    let filename = format!("permission-usage-{}-since-{}.csv", report.tenant_id, report.since);
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename)),
+        ],
+        report.export_csv(&export),
+    )
+        .into_response())
+}
@@ -0,0 +1,122 @@
+//! Admin lite page handlers
+//!
+//! Server-rendered pages over the services behind the `/api/v1/admin`
+//! endpoints, see [`crate::admin_lite`] for the access rules.
+
+use axum::{
+    extract::{Extension, Path, State},
+    http::{header, StatusCode},
+    response::{Html, IntoResponse, Redirect, Response},
+    routing::{get, Router},
+};
+use uuid::Uuid;
+
+use crate::admin_lite::{self, RetentionRow, Viewer};
+use crate::state::AppState;
+
+const SCRIPT: &str = include_str!("../../static/admin-lite/admin-lite.js");
+const STYLESHEET: &str = include_str!("../../static/admin-lite/admin-lite.css");
+
+/// Recent enforcement runs shown on the retention page
+const RETENTION_LOG_LIMIT: i64 = 50;
+
+/// Sign-in page and static assets; only the IP allowlist applies
+pub fn admin_lite_public_routes() -> Router<AppState> {
+    Router::new()
+        .route("/login", get(login))
+        .route("/static/admin-lite.js", get(script))
+        .route("/static/admin-lite.css", get(stylesheet))
+}
+
+/// Pages for authenticated system administrators
+pub fn admin_lite_routes() -> Router<AppState> {
+    Router::new()
+        .route("/", get(|| async { Redirect::to("/admin-lite/tenants") }))
+        .route("/tenants", get(tenants))
+        .route("/tenants/:id", get(tenant))
+        .route("/retention", get(retention))
+}
+
+fn unavailable(what: &str) -> Response {
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        Html(admin_lite::render_message("Unavailable", &format!("Could not load {}.", what))),
+    )
+        .into_response()
+}
+
+async fn login() -> Html<String> {
+    Html(admin_lite::render_login())
+}
+
+async fn script() -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, "text/javascript; charset=utf-8")], SCRIPT)
+}
+
+async fn stylesheet() -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, "text/css; charset=utf-8")], STYLESHEET)
+}
+
+/// Health and migration status of every active tenant, from the health cache
+async fn tenants(State(state): State<AppState>, Extension(viewer): Extension<Viewer>) -> Response {
+    match state.tenant_health.check_all(false).await {
+        Ok(summary) => Html(admin_lite::render_tenants(&summary, &viewer)).into_response(),
+        Err(e) => {
+            tracing::error!("Failed to check tenant health: {}", e);
+            unavailable("tenant health")
+        }
+    }
+}
+
+/// Fresh health checks of one tenant
+async fn tenant(
+    State(state): State<AppState>,
+    Path(tenant_id): Path<Uuid>,
+    Extension(viewer): Extension<Viewer>,
+) -> Response {
+    match state.tenant_health.check_one(tenant_id).await {
+        Ok(Some(report)) => Html(admin_lite::render_tenant(&report, &viewer)).into_response(),
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Html(admin_lite::render_message("Not found", "No active tenant with this id.")),
+        )
+            .into_response(),
+        Err(e) => {
+            tracing::error!("Failed to check health of tenant {}: {}", tenant_id, e);
+            unavailable("tenant health")
+        }
+    }
+}
+
+/// Global retention policies and the latest enforcement runs
+async fn retention(State(state): State<AppState>, Extension(viewer): Extension<Viewer>) -> Response {
+    let retention = &state.retention;
+    let policies = match retention.store().policies(None).await {
+        Ok(policies) => policies,
+        Err(e) => {
+            tracing::error!("Failed to load retention policies: {}", e);
+            return unavailable("retention policies");
+        }
+    };
+    let log = match retention.store().enforcement_log(None, RETENTION_LOG_LIMIT).await {
+        Ok(log) => log,
+        Err(e) => {
+            tracing::error!("Failed to load retention enforcement log: {}", e);
+            return unavailable("the retention log");
+        }
+    };
+
+    let rows: Vec<RetentionRow> = retention
+        .registry()
+        .categories()
+        .map(|category| RetentionRow {
+            category: category.name.clone(),
+            description: category.description.clone(),
+            default_days: category.default_days,
+            default_action: category.default_action,
+            supported_actions: category.supported_actions.clone(),
+            global_policy: policies.iter().find(|p| p.category == category.name).cloned(),
+        })
+        .collect();
+    Html(admin_lite::render_retention(&rows, &log, &viewer)).into_response()
+}
@@ -0,0 +1,98 @@
+//! API usage handlers
+//!
+//! Daily calls of the signed-in user's tenant per access token and endpoint
+//! group, with the calls refused by the rate limit, average latency and bytes
+//! transferred. Reports cover at most 90 days and are read from the stored
+//! usage only; see [`crate::api_usage`]. They need `settings:write`.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json,
+    routing::{get, Router},
+};
+use chrono::NaiveDate;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::api_middleware::tenant_context::TenantUser;
+use crate::state::AppState;
+use erp_core::api_usage::UsageWindow;
+use erp_core::{Error, ErrorCode};
+
+#[derive(Debug, Deserialize)]
+pub struct UsageParams {
+    /// First day of the window; `days` before `to` if not given
+    pub from: Option<NaiveDate>,
+    /// Last day of the window; today if not given
+    pub to: Option<NaiveDate>,
+    pub days: Option<i64>,
+}
+
+/// API usage reports; they need `settings:write`
+pub fn api_usage_routes() -> Router<AppState> {
+    Router::new()
+        .route("/usage/api", get(api_usage))
+        .route("/usage/api/tokens/:token_id", get(token_usage))
+}
+
+fn error_status(e: &Error) -> StatusCode {
+    match e.code {
+        ErrorCode::ValidationFailed => StatusCode::BAD_REQUEST,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+fn window(state: &AppState, params: &UsageParams) -> Result<UsageWindow, (StatusCode, Json<Value>)> {
+    let days = params.days.unwrap_or(state.api_usage.config().default_window_days);
+    UsageWindow::new(params.from, params.to, days)
+        .map_err(|e| (error_status(&e), Json(json!({ "success": false, "error": e.message }))))
+}
+
+/// Calls of the tenant in the window, per day, token and endpoint group
+async fn api_usage(
+    State(state): State<AppState>,
+    Query(params): Query<UsageParams>,
+    TenantUser { tenant_context, .. }: TenantUser,
+) -> Result<(StatusCode, Json<Value>), StatusCode> {
+    let window = match window(&state, &params) {
+        Ok(window) => window,
+        Err(refused) => return Ok(refused),
+    };
+
+    match state.api_usage.store().report(tenant_context.tenant_id.0, window).await {
+        Ok(report) => Ok((StatusCode::OK, Json(json!({ "success": true, "report": report })))),
+        Err(e) => {
+            tracing::error!("Failed to load API usage: {}", e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Calls of one token of the tenant in the window, per day and endpoint group
+async fn token_usage(
+    State(state): State<AppState>,
+    Path(token_id): Path<String>,
+    Query(params): Query<UsageParams>,
+    TenantUser { tenant_context, .. }: TenantUser,
+) -> Result<(StatusCode, Json<Value>), StatusCode> {
+    let window = match window(&state, &params) {
+        Ok(window) => window,
+        Err(refused) => return Ok(refused),
+    };
+
+    match state
+        .api_usage
+        .store()
+        .token_detail(tenant_context.tenant_id.0, &token_id, window)
+        .await
+    {
+        Ok(Some(detail)) => Ok((StatusCode::OK, Json(json!({ "success": true, "usage": detail })))),
+        // Tokens of other tenants are as unknown as tokens that made no calls
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            tracing::error!("Failed to load API usage of token {}: {}", token_id, e);
+            Err(error_status(&e))
+        }
+    }
+}
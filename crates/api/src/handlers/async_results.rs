@@ -0,0 +1,73 @@
+//! Async result handlers
+//!
+//! Results of requests to concurrency-limited endpoints that were sent with
+//! `Prefer: respond-async` (see [`crate::backpressure`]). While the request
+//! waits or runs, its result answers 202 with `Retry-After`; afterwards it
+//! replays the response the endpoint gave, until it expires.
+
+use axum::{
+    body::Body,
+    extract::{Extension, Path, State},
+    http::{header, HeaderValue, StatusCode},
+    response::{IntoResponse, Json, Response},
+    routing::{get, Router},
+};
+use chrono::Utc;
+use serde_json::json;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::backpressure::{AsyncResultStatus, Backpressure};
+use erp_core::{RequestContext, TenantContext};
+
+/// Create async result routes; they need an authenticated user
+pub fn async_result_routes<S>(backpressure: Arc<Backpressure>) -> Router<S> {
+    Router::new()
+        .route("/async-results/:id", get(get_async_result))
+        .with_state(backpressure)
+}
+
+/// Status of the request, or its response once it ran
+async fn get_async_result(
+    State(backpressure): State<Arc<Backpressure>>,
+    Path(id): Path<Uuid>,
+    Extension(tenant_context): Extension<TenantContext>,
+    request_context: RequestContext,
+) -> Result<Response, StatusCode> {
+    let result = match backpressure.results().get(tenant_context.tenant_id.0, id, Utc::now()).await {
+        Ok(result) => result,
+        Err(e) => {
+            tracing::error!("Failed to load async result {}: {}", id, e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+    // Results of other users are not revealed to exist
+    let Some(result) = result.filter(|result| result.user_id == request_context.user_id) else {
+        return Err(StatusCode::NOT_FOUND);
+    };
+
+    match result.status {
+        AsyncResultStatus::Pending => {
+            let mut response = (StatusCode::ACCEPTED, Json(json!({ "success": true, "result": result }))).into_response();
+            response.headers_mut().insert(header::RETRY_AFTER, HeaderValue::from_static("2"));
+            Ok(response)
+        }
+        AsyncResultStatus::Failed => Ok((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({ "success": false, "error": result.error, "result": result })),
+        )
+            .into_response()),
+        AsyncResultStatus::Completed => {
+            let status = result
+                .status_code
+                .and_then(|code| StatusCode::from_u16(code).ok())
+                .unwrap_or(StatusCode::OK);
+            let mut response = Response::new(Body::from(result.body.unwrap_or_default()));
+            *response.status_mut() = status;
+            if let Some(content_type) = result.content_type.and_then(|value| HeaderValue::from_str(&value).ok()) {
+                response.headers_mut().insert(header::CONTENT_TYPE, content_type);
+            }
+            Ok(response)
+        }
+    }
+}
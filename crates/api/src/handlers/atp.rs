@@ -0,0 +1,112 @@
+//! Available-to-promise handlers
+//!
+//! How much of a product can be promised on a day, and from which day a
+//! quantity can be promised: stock on hand at sellable locations less what is
+//! reserved, plus open purchase orders and inbound transfers on their
+//! expected day. The batch variant checks order lines in turn, so each line
+//! only gets what the lines before it left. Nothing is reserved; reading needs
+//! `inventory:read`.
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::Json,
+    routing::{get, post, Router},
+};
+use chrono::{NaiveDate, Utc};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::api_middleware::tenant_context::TenantUser;
+use crate::state::AppState;
+use erp_master_data::inventory::AtpBatchLine;
+use erp_master_data::MasterDataError;
+
+#[derive(Debug, Deserialize)]
+pub struct AtpParams {
+    pub product_id: Uuid,
+    /// Aggregated across all sellable locations if not given
+    pub location_id: Option<Uuid>,
+    /// Quantity to find the earliest day for
+    pub quantity: Option<i64>,
+    /// Day to report the available quantity for; today if not given
+    pub date: Option<NaiveDate>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AtpBatchRequest {
+    pub lines: Vec<AtpBatchLine>,
+}
+
+/// Create available-to-promise routes; they need an authenticated user
+pub fn atp_routes() -> Router<AppState> {
+    Router::new()
+        .route("/", get(get_atp))
+        .route("/batch", post(check_batch))
+}
+
+fn error_status(e: &MasterDataError) -> StatusCode {
+    match e {
+        MasterDataError::NotFoundError(_)
+        | MasterDataError::ProductNotFound { .. }
+        | MasterDataError::LocationNotFound { .. } => StatusCode::NOT_FOUND,
+        MasterDataError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+        MasterDataError::PermissionDenied { .. } => StatusCode::FORBIDDEN,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// Timeline of a product with the quantity available on a day and the earliest day for a quantity
+async fn get_atp(
+    State(state): State<AppState>,
+    Query(params): Query<AtpParams>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+) -> Result<Json<Value>, StatusCode> {
+    if params.quantity.is_some_and(|quantity| quantity <= 0) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let service = state.available_to_promise_service(&tenant_context, &request_context);
+
+    match service.timeline(params.product_id, params.location_id).await {
+        Ok(timeline) => {
+            let date = params.date.unwrap_or_else(|| Utc::now().date_naive()).max(timeline.as_of);
+            let available = timeline.available_on(date);
+            let earliest_date = params.quantity.map(|quantity| timeline.earliest_date(quantity));
+            Ok(Json(json!({
+                "success": true,
+                "date": date,
+                "available_on_date": available,
+                "quantity": params.quantity,
+                "can_promise": params.quantity.map(|quantity| available >= quantity),
+                "earliest_date": earliest_date.flatten(),
+                "timeline": timeline
+            })))
+        }
+        Err(e) => {
+            tracing::error!("Failed to calculate available to promise of product {}: {}", params.product_id, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Check order lines in the order given; each line is promised before the next is checked
+async fn check_batch(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+    Json(request): Json<AtpBatchRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.available_to_promise_service(&tenant_context, &request_context);
+
+    match service.check_lines(&request.lines).await {
+        Ok(lines) => Ok(Json(json!({
+            "success": true,
+            "all_promisable": lines.iter().all(|line| line.can_promise),
+            "lines": lines
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to check available to promise of {} lines: {}", request.lines.len(), e);
+            Err(error_status(&e))
+        }
+    }
+}
@@ -0,0 +1,133 @@
+//! Audit trail handlers
+//!
+//! The audit events of the signed-in user's tenant, filtered by type,
+//! severity, outcome, resource, actor, time range and description text, one
+//! page at a time and newest first. It needs `audit:read`.
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::Json,
+    routing::{get, Router},
+};
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::api_middleware::tenant_context::TenantUser;
+use crate::state::AppState;
+use erp_core::audit::{AuditFilter, PaginationOptions};
+
+#[derive(Debug, Deserialize)]
+pub struct AuditQueryParams {
+    /// Comma-separated event types, e.g. `RESOURCE_UPDATED,DATA_EXPORT`
+    pub event_types: Option<String>,
+    /// Comma-separated severities: `info`, `warning`, `critical`
+    pub severities: Option<String>,
+    /// Comma-separated outcomes: `success`, `failure`, `partial`, `unknown`
+    pub outcomes: Option<String>,
+    pub resource_kind: Option<String>,
+    pub resource_id: Option<String>,
+    pub actor_id: Option<Uuid>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    /// Free text matched against the description
+    pub q: Option<String>,
+    pub page: Option<u32>,
+    pub per_page: Option<u32>,
+}
+
+/// Audit trail of the tenant; it needs `audit:read`
+pub fn audit_routes() -> Router<AppState> {
+    Router::new().route("/", get(query_audit_events))
+}
+
+/// Each comma-separated value in its serialized form; unknown values are a bad request
+fn parse_list<T: DeserializeOwned>(values: Option<&str>) -> Result<Vec<T>, StatusCode> {
+    values
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(|value| serde_json::from_value(Value::String(value.to_string())).map_err(|_| StatusCode::BAD_REQUEST))
+        .collect()
+}
+
+/// Always limited to the tenant of the request
+fn audit_filter(params: &AuditQueryParams, tenant_id: Uuid) -> Result<AuditFilter, StatusCode> {
+    if let (Some(from), Some(to)) = (params.from, params.to) {
+        if from > to {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+    }
+    Ok(AuditFilter {
+        event_types: parse_list(params.event_types.as_deref())?,
+        severities: parse_list(params.severities.as_deref())?,
+        outcomes: parse_list(params.outcomes.as_deref())?,
+        resource_kind: params.resource_kind.clone(),
+        resource_id: params.resource_id.clone(),
+        actor_id: params.actor_id,
+        tenant_id: Some(tenant_id),
+        from: params.from,
+        to: params.to,
+        text_search: params.q.as_deref().map(str::trim).filter(|q| !q.is_empty()).map(str::to_string),
+        ..Default::default()
+    })
+}
+
+/// One page of the tenant's audit events
+async fn query_audit_events(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, .. }: TenantUser,
+    Query(params): Query<AuditQueryParams>,
+) -> Result<Json<Value>, StatusCode> {
+    let tenant_id = tenant_context.tenant_id.0;
+    let filter = audit_filter(&params, tenant_id)?;
+    let pagination = PaginationOptions::new(params.page.unwrap_or(1), params.per_page.unwrap_or(50));
+
+    match state.audit.query(&filter, pagination).await {
+        Ok(result) => Ok(Json(json!({
+            "success": true,
+            "events": result.items,
+            "pagination": {
+                "page": result.page,
+                "per_page": result.per_page,
+                "total": result.total,
+                "total_pages": result.total_pages
+            }
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to query audit events of tenant {}: {}", tenant_id, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use erp_core::audit::{EventSeverity, EventType};
+
+    fn params(query: &str) -> AuditQueryParams {
+        let uri: axum::http::Uri = format!("/api/v1/audit?{}", query).parse().unwrap();
+        Query::<AuditQueryParams>::try_from_uri(&uri).unwrap().0
+    }
+
+    #[test]
+    fn test_filter_parses_lists_and_is_scoped_to_the_tenant() {
+        let tenant = Uuid::new_v4();
+        let filter = audit_filter(&params("event_types=RESOURCE_UPDATED,%20DATA_EXPORT&severities=critical&q=%20limit%20"), tenant).unwrap();
+        assert_eq!(filter.event_types, vec![EventType::ResourceUpdated, EventType::DataExport]);
+        assert_eq!(filter.severities, vec![EventSeverity::Critical]);
+        assert_eq!(filter.tenant_id, Some(tenant));
+        assert_eq!(filter.text_search.as_deref(), Some("limit"));
+
+        assert_eq!(audit_filter(&params("severities=urgent"), tenant).unwrap_err(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            audit_filter(&params("from=2026-02-01T00:00:00Z&to=2026-01-01T00:00:00Z"), tenant).unwrap_err(),
+            StatusCode::BAD_REQUEST
+        );
+    }
+}
@@ -3,16 +3,21 @@
 //! HTTP handlers for authentication endpoints including login, register, 2FA, etc.
 
 use axum::{
-    extract::State,
-    http::StatusCode,
+    extract::{Extension, Path, State},
+    http::{HeaderMap, StatusCode},
     response::{IntoResponse, Json},
-    routing::{post, Router},
+    routing::{delete, get, post, Router},
 };
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 
+use crate::api_middleware::tenant_context::TenantUser;
 use crate::{state::AppState, error_handler::create_api_error};
+use erp_auth::dto::{RevokeSessionsResponse, SessionListResponse, SessionResponse};
 use erp_core::error::{Error, ErrorCode};
+use erp_core::session::SessionState;
+use erp_core::{RequestContext, TenantContext};
+use uuid::Uuid;
 
 #[derive(Debug, Deserialize)]
 pub struct LoginRequest {
@@ -28,6 +33,11 @@ pub struct LoginResponse {
     pub expires_in: Option<i64>,
     pub requires_2fa: Option<bool>,
     pub session_token: Option<String>,
+    /// Sessions the user holds including this one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub active_sessions: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -42,7 +52,10 @@ pub struct RegisterRequest {
 #[derive(Debug, Deserialize)]
 pub struct Verify2FARequest {
     pub session_token: String,
+    #[serde(default)]
     pub code: String,
+    /// Instead of `code`, for users without their authenticator app
+    pub backup_code: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -67,6 +80,33 @@ pub struct VerifyEmailRequest {
     pub token: String,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct AcceptInvitationRequest {
+    pub password: String,
+    pub confirm_password: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChangePasswordRequest {
+    pub current_password: String,
+    pub new_password: String,
+    pub confirm_password: String,
+}
+
+/// Client address (first `X-Forwarded-For` hop) and user agent of a request
+fn client_details(headers: &HeaderMap) -> (Option<String>, Option<String>) {
+    let client_ip = headers
+        .get("x-forwarded-for")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(',').next())
+        .map(|ip| ip.trim().to_string());
+    let user_agent = headers
+        .get("user-agent")
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    (client_ip, user_agent)
+}
+
 /// Create authentication routes
 pub fn auth_routes() -> Router<AppState> {
     Router::new()
@@ -111,9 +151,102 @@ async fn register(
     }
 }
 
+/// Create routes for the signed-in user's own credentials; they need an authenticated user
+pub fn password_routes() -> Router<AppState> {
+    Router::new()
+        .route("/auth/change-password", post(change_password))
+}
+
+/// Create routes for the signed-in user's own sessions; they need an authenticated user
+pub fn session_routes() -> Router<AppState> {
+    Router::new()
+        .route("/auth/sessions", get(list_sessions).delete(revoke_other_sessions))
+        .route("/auth/sessions/:session_id", delete(revoke_session))
+}
+
+/// Create routes for reading another user's sessions; they need `users:read`
+pub fn user_session_read_routes() -> Router<AppState> {
+    Router::new()
+        .route("/users/:id/sessions", get(list_user_sessions))
+}
+
+/// Create routes for signing out another user's sessions; they need `users:update`
+pub fn user_session_admin_routes() -> Router<AppState> {
+    Router::new()
+        .route("/users/:id/sessions", delete(revoke_user_sessions))
+        .route("/users/:id/sessions/:session_id", delete(revoke_user_session))
+}
+
+/// Create routes for accepting a user invitation; the invitation token is the credential
+pub fn invitation_routes() -> Router<AppState> {
+    Router::new()
+        .route("/auth/invitations/:token", get(invitation))
+        .route("/auth/invitations/:token/accept", post(accept_invitation))
+}
+
+/// Responses to invitation links that cannot be used: replaced, accepted or
+/// unknown ones are 400, expired ones 410
+fn invitation_refused(e: &Error) -> Option<StatusCode> {
+    match e.code {
+        ErrorCode::ValidationFailed | ErrorCode::TokenInvalid => Some(StatusCode::BAD_REQUEST),
+        ErrorCode::TokenExpired => Some(StatusCode::GONE),
+        _ => None,
+    }
+}
+
+/// Who is invited to which tenant and by whom, for the acceptance page
+async fn invitation(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+    Extension(tenant_context): Extension<TenantContext>,
+) -> Result<(StatusCode, Json<Value>), StatusCode> {
+    match state.auth_service.invitation(&tenant_context, &token).await {
+        Ok(invitation) => Ok((StatusCode::OK, Json(json!({ "success": true, "invitation": invitation })))),
+        Err(e) => match invitation_refused(&e) {
+            Some(status) => Ok((status, Json(json!({ "success": false, "error": e.message })))),
+            None => {
+                tracing::error!("Failed to load invitation: {}", e);
+                Err(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        },
+    }
+}
+
+/// Accept an invitation by choosing a password; the user can sign in afterwards
+async fn accept_invitation(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+    Extension(tenant_context): Extension<TenantContext>,
+    headers: HeaderMap,
+    Json(payload): Json<AcceptInvitationRequest>,
+) -> Result<(StatusCode, Json<Value>), StatusCode> {
+    let (client_ip, _) = client_details(&headers);
+    let request = erp_auth::dto::AcceptInvitationRequest {
+        password: payload.password,
+        confirm_password: payload.confirm_password,
+    };
+
+    match state.auth_service.accept_invitation(&tenant_context, &token, request, client_ip).await {
+        Ok(user) => Ok((StatusCode::OK, Json(json!({
+            "success": true,
+            "user": user,
+            "message": "Invitation accepted. You can now sign in."
+        })))),
+        Err(e) => match invitation_refused(&e) {
+            Some(status) => Ok((status, Json(json!({ "success": false, "error": e.message })))),
+            None => {
+                tracing::error!("Failed to accept invitation: {}", e);
+                Err(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        },
+    }
+}
+
 /// User login
 async fn login(
     State(state): State<AppState>,
+    tenant_context: Option<Extension<TenantContext>>,
+    headers: HeaderMap,
     Json(payload): Json<LoginRequest>,
 ) -> Result<Json<LoginResponse>, StatusCode> {
     // Validate input
@@ -125,12 +258,17 @@ async fn login(
             expires_in: None,
             requires_2fa: None,
             session_token: None,
+            active_sessions: None,
+            error: None,
         }));
     }
 
-    // For now, use a default tenant ID (in production, this would come from subdomain or header)
-    let tenant_id = uuid::Uuid::parse_str("550e8400-e29b-41d4-a716-446655440000")
-        .unwrap_or_else(|_| uuid::Uuid::new_v4());
+    // The tenant comes from the X-Tenant-ID header or subdomain; without one, the demo tenant
+    let tenant_id = match tenant_context {
+        Some(Extension(tenant_context)) => tenant_context.tenant_id.0,
+        None => uuid::Uuid::parse_str("550e8400-e29b-41d4-a716-446655440000")
+            .unwrap_or_else(|_| uuid::Uuid::new_v4()),
+    };
 
     // Create login request for auth service
     let auth_request = erp_auth::dto::LoginRequest {
@@ -139,7 +277,8 @@ async fn login(
     };
 
     // Call the auth service
-    match state.auth_service.login(tenant_id, auth_request, None, None).await {
+    let (client_ip, user_agent) = client_details(&headers);
+    match state.auth_service.login(tenant_id, auth_request, client_ip, user_agent).await {
         Ok(response) => {
             match response {
                 erp_auth::LoginOrTwoFactorResponse::Success(login_resp) => {
@@ -150,6 +289,8 @@ async fn login(
                         expires_in: Some(1800), // 30 minutes
                         requires_2fa: Some(false),
                         session_token: None,
+                        active_sessions: login_resp.active_sessions,
+                        error: None,
                     }))
                 },
                 erp_auth::LoginOrTwoFactorResponse::TwoFactorRequired(tfa_resp) => {
@@ -160,12 +301,15 @@ async fn login(
                         expires_in: None,
                         requires_2fa: Some(true),
                         session_token: Some(tfa_resp.login_session_token),
+                        active_sessions: None,
+                        error: None,
                     }))
                 }
             }
         },
-        Err(_) => {
-            // Authentication failed
+        Err(e) => {
+            // The tenant refuses logins beyond its session limit; everything else reads as bad credentials
+            let error = (e.code == ErrorCode::ConcurrencyLimitExceeded).then(|| e.message.clone());
             Ok(Json(LoginResponse {
                 success: false,
                 access_token: None,
@@ -173,6 +317,8 @@ async fn login(
                 expires_in: None,
                 requires_2fa: None,
                 session_token: None,
+                active_sessions: None,
+                error,
             }))
         }
     }
@@ -187,6 +333,7 @@ async fn verify_2fa(
     let verify_request = erp_auth::dto::Verify2FARequest {
         login_session_token: payload.session_token,
         code: payload.code,
+        backup_code: payload.backup_code,
     };
 
     // Call the auth service
@@ -195,7 +342,8 @@ async fn verify_2fa(
             "success": true,
             "access_token": response.access_token,
             "refresh_token": response.refresh_token,
-            "expires_in": 1800
+            "expires_in": 1800,
+            "active_sessions": response.active_sessions
         }))),
         Err(e) => {
             tracing::error!("2FA verification failed: {}", e);
@@ -332,6 +480,171 @@ async fn verify_email(
     }
 }
 
+/// Change the signed-in user's password
+///
+/// Every other session is signed out; the response carries a fresh token pair
+/// for the caller, whose previous tokens stop working as well.
+async fn change_password(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, user_id, .. }: TenantUser,
+    headers: HeaderMap,
+    Json(payload): Json<ChangePasswordRequest>,
+) -> impl IntoResponse {
+    let (client_ip, user_agent) = client_details(&headers);
+
+    let change_request = erp_auth::dto::ChangePasswordRequest {
+        current_password: payload.current_password,
+        new_password: payload.new_password,
+        confirm_password: payload.confirm_password,
+    };
+
+    match state.auth_service
+        .change_password(&tenant_context, user_id, change_request, client_ip, user_agent)
+        .await
+    {
+        Ok(response) => Json(json!({
+            "success": true,
+            "message": "Password has been changed. Other sessions have been signed out.",
+            "access_token": response.access_token,
+            "refresh_token": response.refresh_token,
+            "expires_in": 1800,
+            "active_sessions": response.active_sessions
+        })).into_response(),
+        Err(e) => {
+            tracing::warn!("Password change failed for user {}: {}", user_id, e);
+            create_api_error(e).into_response()
+        }
+    }
+}
+
+/// The user's sessions, most recently active first, with the caller's own flagged
+async fn session_list(state: &AppState, tenant_id: Uuid, user_id: Uuid, current: Option<&str>) -> Result<SessionListResponse, Error> {
+    let mut sessions = state.auth_service.get_user_sessions(tenant_id, user_id).await?;
+    sessions.sort_by_key(|session| std::cmp::Reverse(session.last_activity));
+    Ok(SessionListResponse {
+        success: true,
+        sessions: sessions.iter().map(|session| SessionResponse::of(session, current)).collect(),
+    })
+}
+
+/// Signs out one session of `user_id`; the caller's own session takes its
+/// access token with it, the way `logout` does
+async fn end_session(
+    state: &AppState,
+    tenant_id: Uuid,
+    user_id: Uuid,
+    session_id: &str,
+    reason: SessionState,
+    request_context: &RequestContext,
+    headers: &HeaderMap,
+) -> Result<(), Error> {
+    state.auth_service.revoke_session(tenant_id, user_id, session_id, reason).await?;
+    if request_context.session_id.as_deref() == Some(session_id) {
+        if let Some(jti) = &request_context.jti {
+            let (client_ip, _) = client_details(headers);
+            state.auth_service.logout(jti, client_ip).await?;
+        }
+    }
+    Ok(())
+}
+
+/// The signed-in user's sessions; the one of this request is flagged `current`
+async fn list_sessions(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, request_context, user_id }: TenantUser,
+) -> impl IntoResponse {
+    match session_list(&state, tenant_context.tenant_id.0, user_id, request_context.session_id.as_deref()).await {
+        Ok(response) => Json(response).into_response(),
+        Err(e) => {
+            tracing::error!("Failed to list sessions of user {}: {}", user_id, e);
+            create_api_error(e).into_response()
+        }
+    }
+}
+
+/// Sign out one of the signed-in user's sessions, possibly this one
+async fn revoke_session(
+    State(state): State<AppState>,
+    Path(session_id): Path<String>,
+    TenantUser { tenant_context, request_context, user_id }: TenantUser,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let tenant_id = tenant_context.tenant_id.0;
+    match end_session(&state, tenant_id, user_id, &session_id, SessionState::LoggedOut, &request_context, &headers).await {
+        Ok(()) => Json(RevokeSessionsResponse { success: true, revoked_sessions: 1 }).into_response(),
+        Err(e) => {
+            tracing::warn!("Failed to revoke session {} of user {}: {}", session_id, user_id, e);
+            create_api_error(e).into_response()
+        }
+    }
+}
+
+/// Sign out every session of the signed-in user except this one
+async fn revoke_other_sessions(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, request_context, user_id }: TenantUser,
+) -> impl IntoResponse {
+    let current = request_context.session_id.as_deref();
+    match state.auth_service
+        .revoke_other_sessions(tenant_context.tenant_id.0, user_id, current, SessionState::LoggedOut)
+        .await
+    {
+        Ok(revoked_sessions) => Json(RevokeSessionsResponse { success: true, revoked_sessions }).into_response(),
+        Err(e) => {
+            tracing::error!("Failed to revoke other sessions of user {}: {}", user_id, e);
+            create_api_error(e).into_response()
+        }
+    }
+}
+
+/// Another user's sessions, for administrators
+async fn list_user_sessions(
+    State(state): State<AppState>,
+    Path(user_id): Path<Uuid>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+) -> impl IntoResponse {
+    match session_list(&state, tenant_context.tenant_id.0, user_id, request_context.session_id.as_deref()).await {
+        Ok(response) => Json(response).into_response(),
+        Err(e) => {
+            tracing::error!("Failed to list sessions of user {}: {}", user_id, e);
+            create_api_error(e).into_response()
+        }
+    }
+}
+
+/// Revoke one of another user's sessions, for administrators
+async fn revoke_user_session(
+    State(state): State<AppState>,
+    Path((user_id, session_id)): Path<(Uuid, String)>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let tenant_id = tenant_context.tenant_id.0;
+    match end_session(&state, tenant_id, user_id, &session_id, SessionState::Revoked, &request_context, &headers).await {
+        Ok(()) => Json(RevokeSessionsResponse { success: true, revoked_sessions: 1 }).into_response(),
+        Err(e) => {
+            tracing::warn!("Failed to revoke session {} of user {}: {}", session_id, user_id, e);
+            create_api_error(e).into_response()
+        }
+    }
+}
+
+/// Revoke every session of another user, for administrators
+async fn revoke_user_sessions(
+    State(state): State<AppState>,
+    Path(user_id): Path<Uuid>,
+    TenantUser { tenant_context, user_id: admin_id, .. }: TenantUser,
+) -> impl IntoResponse {
+    let reason = format!("Revoked by user {}", admin_id);
+    match state.auth_service.revoke_user_sessions(tenant_context.tenant_id.0, user_id, &reason).await {
+        Ok(revoked_sessions) => Json(RevokeSessionsResponse { success: true, revoked_sessions }).into_response(),
+        Err(e) => {
+            tracing::error!("Failed to revoke sessions of user {}: {}", user_id, e);
+            create_api_error(e).into_response()
+        }
+    }
+}
+
 /// User logout
 async fn logout(State(_state): State<AppState>) -> Result<Json<Value>, StatusCode> {
     // In production, we would get the session ID from the token
@@ -0,0 +1,254 @@
+//! Business calendar handlers
+//!
+//! The working weekdays and holidays of the signed-in user's tenant, and the
+//! working-day queries jobs and clients schedule by. See
+//! [`crate::business_calendar`] and [`erp_core::calendar`].
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json,
+    routing::{get, post, put, Router},
+};
+use chrono::{Datelike, Duration, NaiveDate, Utc};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::api_middleware::tenant_context::TenantUser;
+use crate::{
+    business_calendar::{calendar_around, import_holidays, CalendarSettings},
+    state::AppState,
+};
+use erp_core::calendar::{CalendarDay, CalendarPolicy, DaySource};
+use erp_core::{Error, ErrorCode};
+
+/// Longest range of days listed at once
+const MAX_LIST_DAYS: i64 = 731;
+
+#[derive(Debug, Deserialize)]
+pub struct CalendarDaysParams {
+    /// Defaults to January 1st of the current year
+    pub from: Option<NaiveDate>,
+    /// Defaults to December 31st of `from`'s year
+    pub to: Option<NaiveDate>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WorkingDayParams {
+    pub date: NaiveDate,
+    /// Also report where a run scheduled on `date` lands under this policy
+    pub policy: Option<CalendarPolicy>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CalendarDayRequest {
+    pub label: String,
+    /// `false` (the default) for a holiday, `true` to work on an otherwise free date
+    #[serde(default)]
+    pub working_day: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ImportHolidaysRequest {
+    /// ISO 3166-1 alpha-2 code, e.g. `DE`
+    pub country: String,
+    pub year: i32,
+}
+
+/// Calendar reads; they need an authenticated user
+pub fn calendar_routes() -> Router<AppState> {
+    Router::new()
+        .route("/calendar", get(get_calendar))
+        .route("/calendar/days", get(list_days))
+        .route("/calendar/working-day", get(working_day))
+}
+
+/// Calendar changes; they need `settings:write`
+pub fn calendar_admin_routes() -> Router<AppState> {
+    Router::new()
+        .route("/calendar", put(update_calendar))
+        .route("/calendar/days/:date", put(save_day).delete(delete_day))
+        .route("/calendar/import", post(import_country_holidays))
+}
+
+fn error_status(e: &Error) -> StatusCode {
+    match e.code {
+        ErrorCode::ValidationFailed => StatusCode::BAD_REQUEST,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// Working weekdays; tenants without a calendar work every day
+async fn get_calendar(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, .. }: TenantUser,
+) -> Result<Json<Value>, StatusCode> {
+
+    match state.calendars.settings(tenant_context.tenant_id.0).await {
+        Ok(settings) => Ok(Json(json!({
+            "success": true,
+            "configured": settings.is_some(),
+            "calendar": settings.unwrap_or(CalendarSettings {
+                working_days: (1..=7).collect(),
+                country: None,
+            })
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to load business calendar: {}", e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Set the working weekdays
+async fn update_calendar(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, .. }: TenantUser,
+    Json(mut settings): Json<CalendarSettings>,
+) -> Result<(StatusCode, Json<Value>), StatusCode> {
+    settings.working_days.sort_unstable();
+    settings.working_days.dedup();
+    if let Err(e) = settings.validate() {
+        return Ok((StatusCode::BAD_REQUEST, Json(json!({ "success": false, "error": e.message }))));
+    }
+
+    match state.calendars.save_settings(tenant_context.tenant_id.0, &settings).await {
+        Ok(()) => Ok((StatusCode::OK, Json(json!({ "success": true, "calendar": settings })))),
+        Err(e) => {
+            tracing::error!("Failed to save business calendar: {}", e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Holidays and extra working days in a date range
+async fn list_days(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, .. }: TenantUser,
+    Query(params): Query<CalendarDaysParams>,
+) -> Result<Json<Value>, StatusCode> {
+    let from = params
+        .from
+        .unwrap_or_else(|| NaiveDate::from_ymd_opt(Utc::now().year(), 1, 1).expect("valid date"));
+    let to = params
+        .to
+        .unwrap_or_else(|| NaiveDate::from_ymd_opt(from.year(), 12, 31).expect("valid date"));
+    if to < from || to - from > Duration::days(MAX_LIST_DAYS) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    match state.calendars.days(tenant_context.tenant_id.0, from, to).await {
+        Ok(days) => Ok(Json(json!({
+            "success": true,
+            "from": from,
+            "to": to,
+            "days": days
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to list business calendar days: {}", e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Add or replace the holiday or extra working day on a date
+async fn save_day(
+    State(state): State<AppState>,
+    Path(date): Path<NaiveDate>,
+    TenantUser { tenant_context, .. }: TenantUser,
+    Json(request): Json<CalendarDayRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    let label = request.label.trim();
+    if label.is_empty() || label.len() > 100 {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let day = CalendarDay {
+        date,
+        label: label.to_string(),
+        working_day: request.working_day,
+        source: DaySource::Manual,
+        country: None,
+    };
+    match state.calendars.save_day(tenant_context.tenant_id.0, &day).await {
+        Ok(()) => Ok(Json(json!({ "success": true, "day": day }))),
+        Err(e) => {
+            tracing::error!("Failed to save business calendar day {}: {}", date, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Remove the exception on a date, imported or manual
+async fn delete_day(
+    State(state): State<AppState>,
+    Path(date): Path<NaiveDate>,
+    TenantUser { tenant_context, .. }: TenantUser,
+) -> Result<Json<Value>, StatusCode> {
+
+    match state.calendars.delete_day(tenant_context.tenant_id.0, date).await {
+        Ok(true) => Ok(Json(json!({ "success": true }))),
+        Ok(false) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            tracing::error!("Failed to delete business calendar day {}: {}", date, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Import a country's public holidays for one year; manual entries are kept
+async fn import_country_holidays(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, .. }: TenantUser,
+    Json(request): Json<ImportHolidaysRequest>,
+) -> Result<(StatusCode, Json<Value>), StatusCode> {
+    if !(1900..=2200).contains(&request.year) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    match import_holidays(state.calendars.as_ref(), tenant_context.tenant_id.0, &request.country, request.year).await {
+        Ok(imported) => Ok((StatusCode::OK, Json(json!({
+            "success": true,
+            "country": request.country.to_ascii_uppercase(),
+            "year": request.year,
+            "imported": imported
+        })))),
+        Err(e) if e.code == ErrorCode::ValidationFailed => {
+            Ok((StatusCode::BAD_REQUEST, Json(json!({ "success": false, "error": e.message }))))
+        }
+        Err(e) => {
+            tracing::error!("Failed to import holidays: {}", e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Whether a date is a working day, the next working day, and with a policy
+/// where a run scheduled on the date lands
+async fn working_day(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, .. }: TenantUser,
+    Query(params): Query<WorkingDayParams>,
+) -> Result<Json<Value>, StatusCode> {
+
+    match calendar_around(state.calendars.as_ref(), tenant_context.tenant_id.0, params.date).await {
+        Ok(calendar) => {
+            let mut body = json!({
+                "success": true,
+                "date": params.date,
+                "working_day": calendar.is_working_day(params.date),
+                "day": calendar.day(params.date),
+                "next_working_day": calendar.next_working_day(params.date),
+            });
+            if let Some(policy) = params.policy {
+                body["policy"] = json!(policy);
+                body["run_date"] = json!(calendar.run_date(policy, params.date));
+            }
+            Ok(Json(body))
+        }
+        Err(e) => {
+            tracing::error!("Failed to load business calendar: {}", e);
+            Err(error_status(&e))
+        }
+    }
+}
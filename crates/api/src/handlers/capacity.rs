@@ -0,0 +1,171 @@
+//! Location capacity and purchase order receipt handlers
+//!
+//! Locations and their bins can have a storage capacity in pallet positions,
+//! cubic meters or units. Stock transfers and purchase order receipts into
+//! such a location are checked against it: close to capacity they go through
+//! with a warning, above it they need `override_capacity` and the
+//! `inventory:capacity_override` permission. Every call acts as the
+//! authenticated user.
+//!
+//! Receipts are booked with `POST /purchase-orders/:id/receipts` (or the
+//! older `/receive`) by users who may change inventory; the order's receipt
+//! history, every receipt and close-short event per line, is read with
+//! `GET /purchase-orders/:id/receipts`.
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+    routing::{get, post, put, Router},
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::api_middleware::tenant_context::TenantUser;
+use crate::state::AppState;
+use erp_master_data::inventory::{CapacityUnit, LocationCapacityInput, ReceivePurchaseOrderRequest};
+use erp_master_data::MasterDataError;
+
+#[derive(Debug, Deserialize)]
+pub struct SetCapacitiesRequest {
+    /// Capacities of the whole location (no `bin_code`) and of single bins
+    pub capacities: Vec<LocationCapacityInput>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SetSpaceFactorsRequest {
+    /// Space one unit takes, per capacity unit; units left out are derived from the dimensions
+    pub space_per_unit: HashMap<CapacityUnit, f64>,
+}
+
+/// Create location capacity routes; they need an authenticated user
+pub fn capacity_routes() -> Router<AppState> {
+    Router::new()
+        .route("/locations/:location_id", get(get_location_capacity).put(set_location_capacity))
+        .route("/products/:product_id/space-factors", put(set_space_factors))
+}
+
+/// Create purchase order receipt routes; they need an authenticated user
+pub fn receiving_routes() -> Router<AppState> {
+    Router::new()
+        .route("/:id/receipts", get(get_receipt_history).post(receive_purchase_order))
+        .route("/:id/receive", post(receive_purchase_order))
+}
+
+fn error_status(e: &MasterDataError) -> StatusCode {
+    match e {
+        MasterDataError::NotFoundError(_) | MasterDataError::ProductNotFound { .. } => StatusCode::NOT_FOUND,
+        MasterDataError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+        MasterDataError::PermissionDenied { .. } => StatusCode::FORBIDDEN,
+        MasterDataError::LocationCapacityExceeded { .. } => StatusCode::CONFLICT,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// Utilization of each capacity of the location, including stock on its way there
+async fn get_location_capacity(
+    State(state): State<AppState>,
+    Path(location_id): Path<Uuid>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.location_capacity_service(&tenant_context, &request_context);
+
+    match service.get_utilization(location_id).await {
+        Ok(utilization) => Ok(Json(json!({
+            "success": true,
+            "location_id": location_id,
+            "utilization": utilization.usages,
+            "estimated_products": utilization.estimated_products
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to get capacity of location {}: {}", location_id, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Replace the capacities of the location and its bins
+async fn set_location_capacity(
+    State(state): State<AppState>,
+    Path(location_id): Path<Uuid>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+    Json(request): Json<SetCapacitiesRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.location_capacity_service(&tenant_context, &request_context);
+
+    match service.set_capacities(location_id, request.capacities).await {
+        Ok(capacities) => Ok(Json(json!({
+            "success": true,
+            "capacities": capacities
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to set capacity of location {}: {}", location_id, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Replace the explicit space per unit of a product
+async fn set_space_factors(
+    State(state): State<AppState>,
+    Path(product_id): Path<Uuid>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+    Json(request): Json<SetSpaceFactorsRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.location_capacity_service(&tenant_context, &request_context);
+
+    match service.set_space_overrides(product_id, request.space_per_unit.clone()).await {
+        Ok(()) => Ok(Json(json!({
+            "success": true,
+            "product_id": product_id,
+            "space_per_unit": request.space_per_unit
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to set space factors of product {}: {}", product_id, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Book delivered units of a purchase order into its location
+async fn receive_purchase_order(
+    State(state): State<AppState>,
+    Path(purchase_order_id): Path<Uuid>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+    Json(request): Json<ReceivePurchaseOrderRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.purchase_receipt_service(&tenant_context, &request_context);
+
+    match service.receive_purchase_order(purchase_order_id, request).await {
+        Ok(receipt) => Ok(Json(json!({
+            "success": true,
+            "receipt": receipt
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to receive purchase order {}: {}", purchase_order_id, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Every receipt event of a purchase order, per line
+async fn get_receipt_history(
+    State(state): State<AppState>,
+    Path(purchase_order_id): Path<Uuid>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.purchase_receipt_service(&tenant_context, &request_context);
+
+    match service.receipt_history(purchase_order_id).await {
+        Ok(history) => Ok(Json(json!({
+            "success": true,
+            "history": history
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to get receipts of purchase order {}: {}", purchase_order_id, e);
+            Err(error_status(&e))
+        }
+    }
+}
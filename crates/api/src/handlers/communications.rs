@@ -0,0 +1,248 @@
+//! Customer communication log handlers
+//!
+//! Emails, calls, meetings and notes per customer, the signed-in user's
+//! follow-ups across customers, and the customer summary with recent contact.
+
+use axum::{
+    extract::{Extension, Path, Query, State},
+    http::StatusCode,
+    response::Json,
+    routing::{get, put, Router},
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::api_middleware::tenant_context::TenantUser;
+use crate::{api_middleware::api_version::ApiVersion, responses::customer_response, state::AppState};
+use erp_master_data::customer::{CreateCommunicationRequest, UpdateCommunicationRequest};
+use erp_master_data::MasterDataError;
+
+/// Entries shown on the customer summary
+const RECENT_COMMUNICATIONS: u32 = 5;
+
+#[derive(Debug, Deserialize)]
+pub struct CommunicationListParams {
+    #[serde(default = "default_limit")]
+    pub limit: u32,
+    #[serde(default)]
+    pub offset: u32,
+}
+
+fn default_limit() -> u32 { 50 }
+
+#[derive(Debug, Deserialize)]
+pub struct FollowUpParams {
+    #[serde(default = "default_horizon_days")]
+    pub horizon_days: u32,
+}
+
+fn default_horizon_days() -> u32 { 7 }
+
+#[derive(Debug, Deserialize)]
+pub struct ReminderPreferenceRequest {
+    pub email_enabled: bool,
+}
+
+/// Create communication routes; they need an authenticated user
+pub fn communication_routes() -> Router<AppState> {
+    Router::new()
+        .route("/customers/:id/communications", get(list_communications).post(log_communication))
+        .route(
+            "/customers/:id/communications/:communication_id",
+            get(get_communication).put(update_communication).delete(delete_communication),
+        )
+        .route("/customers/:id/summary", get(get_customer_summary))
+        .route("/communications/follow-ups", get(my_follow_ups))
+        .route("/communications/follow-ups/reminders", put(set_reminder_preference))
+}
+
+fn error_status(e: &MasterDataError) -> StatusCode {
+    match e {
+        MasterDataError::CustomerNotFound { .. } | MasterDataError::CommunicationNotFound { .. } => StatusCode::NOT_FOUND,
+        MasterDataError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+        MasterDataError::PermissionDenied { .. } => StatusCode::FORBIDDEN,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// Communications of a customer visible to the user, newest first
+async fn list_communications(
+    State(state): State<AppState>,
+    Path(customer_id): Path<Uuid>,
+    Query(params): Query<CommunicationListParams>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.communication_service(&tenant_context, &request_context);
+
+    match service.list_communications(customer_id, params.limit, params.offset).await {
+        Ok(communications) => Ok(Json(json!({
+            "success": true,
+            "customer_id": customer_id,
+            "count": communications.len(),
+            "communications": communications
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to list communications for customer {}: {}", customer_id, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Log an email, call, meeting or note
+async fn log_communication(
+    State(state): State<AppState>,
+    Path(customer_id): Path<Uuid>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+    Json(request): Json<CreateCommunicationRequest>,
+) -> Result<(StatusCode, Json<Value>), StatusCode> {
+    let service = state.communication_service(&tenant_context, &request_context);
+
+    match service.log_communication(customer_id, request).await {
+        Ok(communication) => Ok((StatusCode::CREATED, Json(json!({
+            "success": true,
+            "communication": communication
+        })))),
+        Err(e) => {
+            tracing::error!("Failed to log communication for customer {}: {}", customer_id, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+async fn get_communication(
+    State(state): State<AppState>,
+    Path((customer_id, communication_id)): Path<(Uuid, Uuid)>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.communication_service(&tenant_context, &request_context);
+
+    match service.get_communication(customer_id, communication_id).await {
+        Ok(communication) => Ok(Json(json!({
+            "success": true,
+            "communication": communication
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to get communication {}: {}", communication_id, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+async fn update_communication(
+    State(state): State<AppState>,
+    Path((customer_id, communication_id)): Path<(Uuid, Uuid)>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+    Json(request): Json<UpdateCommunicationRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.communication_service(&tenant_context, &request_context);
+
+    match service.update_communication(customer_id, communication_id, request).await {
+        Ok(communication) => Ok(Json(json!({
+            "success": true,
+            "communication": communication
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to update communication {}: {}", communication_id, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+async fn delete_communication(
+    State(state): State<AppState>,
+    Path((customer_id, communication_id)): Path<(Uuid, Uuid)>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.communication_service(&tenant_context, &request_context);
+
+    match service.delete_communication(customer_id, communication_id).await {
+        Ok(()) => Ok(Json(json!({
+            "success": true,
+            "message": format!("Communication {} deleted successfully", communication_id)
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to delete communication {}: {}", communication_id, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Customer with the most recent communications visible to the user
+async fn get_customer_summary(
+    State(state): State<AppState>,
+    Path(customer_id): Path<Uuid>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+    Extension(version): Extension<ApiVersion>,
+) -> Result<Json<Value>, StatusCode> {
+    let communications = state.communication_service(&tenant_context, &request_context);
+
+    let customer = match state.customer_service(tenant_context).get_customer(customer_id).await {
+        Ok(Some(customer)) => customer,
+        Ok(None) => return Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            tracing::error!("Failed to get customer {}: {}", customer_id, e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    match communications.list_communications(customer_id, RECENT_COMMUNICATIONS, 0).await {
+        Ok(recent) => Ok(Json(json!({
+            "success": true,
+            "customer": customer_response(version, customer)?,
+            "recent_communications": recent
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to load recent communications for customer {}: {}", customer_id, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// The signed-in user's open follow-ups across customers
+async fn my_follow_ups(
+    State(state): State<AppState>,
+    Query(params): Query<FollowUpParams>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.communication_service(&tenant_context, &request_context);
+
+    match service.my_follow_ups(params.horizon_days).await {
+        Ok(due) => Ok(Json(json!({
+            "success": true,
+            "horizon_days": params.horizon_days,
+            "overdue": due.overdue,
+            "upcoming": due.upcoming
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to load follow-ups: {}", e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Turn the daily follow-up reminder email on or off for the signed-in user
+async fn set_reminder_preference(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+    Json(request): Json<ReminderPreferenceRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    state.communication_service(&tenant_context, &request_context);
+    let user_id = request_context.user_id.ok_or(StatusCode::FORBIDDEN)?;
+
+    match state
+        .follow_up_reminders
+        .directory()
+        .set_reminders_enabled(tenant_context.tenant_id.0, user_id, request.email_enabled)
+        .await
+    {
+        Ok(()) => Ok(Json(json!({
+            "success": true,
+            "email_enabled": request.email_enabled
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to update reminder preference for user {}: {}", user_id, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
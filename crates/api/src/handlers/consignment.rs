@@ -0,0 +1,163 @@
+//! Consignment stock handlers
+//!
+//! Stock suppliers keep in our locations stays theirs until it is sold or
+//! taken over into our own stock; only then is it owed to the supplier, at
+//! the price of their catalog entry. It is not part of the stock valuation.
+//! Reading needs `inventory:read`, booking `inventory:write`. Every call acts
+//! as the authenticated user.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json,
+    routing::{get, post, Router},
+};
+use chrono::NaiveDate;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::api_middleware::tenant_context::TenantUser;
+use crate::state::AppState;
+use erp_master_data::inventory::{ConsignmentConsumptionRequest, ConsignmentReceiptRequest, ConsignmentTransferRequest};
+use erp_master_data::MasterDataError;
+
+#[derive(Debug, Deserialize)]
+pub struct StockParams {
+    pub supplier_id: Option<Uuid>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SettlementParams {
+    pub from: NaiveDate,
+    /// Inclusive
+    pub to: NaiveDate,
+}
+
+/// Create consignment routes; they need an authenticated user
+pub fn consignment_routes() -> Router<AppState> {
+    Router::new()
+        .route("/stock", get(list_stock))
+        .route("/receipts", post(receive))
+        .route("/consumptions", post(consume))
+        .route("/transfers", post(transfer))
+        .route("/suppliers/:supplier_id/settlement", get(get_settlement))
+}
+
+fn error_status(e: &MasterDataError) -> StatusCode {
+    match e {
+        MasterDataError::NotFoundError(_)
+        | MasterDataError::SupplierNotFound { .. }
+        | MasterDataError::ProductNotFound { .. }
+        | MasterDataError::LocationNotFound { .. } => StatusCode::NOT_FOUND,
+        MasterDataError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+        MasterDataError::PermissionDenied { .. } => StatusCode::FORBIDDEN,
+        MasterDataError::LocationCapacityExceeded { .. } => StatusCode::CONFLICT,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// Suppliers' stock on hand per product and location
+async fn list_stock(
+    State(state): State<AppState>,
+    Query(params): Query<StockParams>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.consignment_service(&tenant_context, &request_context);
+
+    match service.balances(params.supplier_id).await {
+        Ok(stock) => Ok(Json(json!({
+            "success": true,
+            "stock": stock
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to list consignment stock: {}", e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Book units delivered on consignment into a location
+async fn receive(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+    Json(request): Json<ConsignmentReceiptRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.consignment_service(&tenant_context, &request_context);
+    let supplier_id = request.supplier_id;
+
+    match service.receive(request).await {
+        Ok(movement) => Ok(Json(json!({
+            "success": true,
+            "movement": movement
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to receive consignment stock of supplier {}: {}", supplier_id, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Sell units of a supplier or take them over into our own stock
+async fn consume(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+    Json(request): Json<ConsignmentConsumptionRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.consignment_service(&tenant_context, &request_context);
+    let supplier_id = request.supplier_id;
+
+    match service.consume(request).await {
+        Ok(movement) => Ok(Json(json!({
+            "success": true,
+            "movement": movement,
+            "amount": movement.amount()
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to consume consignment stock of supplier {}: {}", supplier_id, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Move units of a supplier to another location
+async fn transfer(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+    Json(request): Json<ConsignmentTransferRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.consignment_service(&tenant_context, &request_context);
+    let supplier_id = request.supplier_id;
+
+    match service.transfer(request).await {
+        Ok(movements) => Ok(Json(json!({
+            "success": true,
+            "movements": movements
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to transfer consignment stock of supplier {}: {}", supplier_id, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// The supplier's stock on hand and what is owed for the period's consumption
+async fn get_settlement(
+    State(state): State<AppState>,
+    Path(supplier_id): Path<Uuid>,
+    Query(params): Query<SettlementParams>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.consignment_service(&tenant_context, &request_context);
+
+    match service.settlement(supplier_id, params.from, params.to).await {
+        Ok(settlement) => Ok(Json(json!({
+            "success": true,
+            "settlement": settlement
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to build consignment settlement of supplier {}: {}", supplier_id, e);
+            Err(error_status(&e))
+        }
+    }
+}
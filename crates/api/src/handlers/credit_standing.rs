@@ -0,0 +1,78 @@
+//! Customer credit standing handlers
+//!
+//! The credit status feed the accounting system polls for dunning, and the
+//! open items import it pushes customer balances through. See
+//! [`crate::credit_standing`] for how the standing is recomputed.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json,
+    routing::{get, post, Router},
+};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::api_middleware::tenant_context::TenantUser;
+use crate::{
+    credit_standing::ImportOpenItemsRequest,
+    handlers::sync::{changes_since, SyncParams},
+    state::AppState,
+    sync::SyncEntity,
+};
+use erp_core::{Error, ErrorCode};
+
+/// Credit status feed; it needs a user who may read customers
+pub fn credit_feed_routes() -> Router<AppState> {
+    Router::new().route("/customers/credit-status-feed", get(credit_status_feed))
+}
+
+/// Open items import; it needs a user who may change customers
+pub fn open_item_routes() -> Router<AppState> {
+    Router::new().route("/customers/:id/open-items", post(import_open_items))
+}
+
+fn error_status(e: &Error) -> StatusCode {
+    match e.code {
+        ErrorCode::ValidationFailed => StatusCode::BAD_REQUEST,
+        ErrorCode::NotFound => StatusCode::NOT_FOUND,
+        ErrorCode::ConflictError => StatusCode::CONFLICT,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// Customers whose credit standing, credit limit or credit status changed since the cursor
+async fn credit_status_feed(
+    State(state): State<AppState>,
+    user: TenantUser,
+    Query(params): Query<SyncParams>,
+) -> Result<Json<Value>, StatusCode> {
+    let TenantUser { tenant_context, .. } = user.staff_only()?;
+    changes_since(&state, &tenant_context, SyncEntity::CustomerCredit, params.since).await
+}
+
+/// Upsert the customer's open items by document number and recompute its credit standing
+async fn import_open_items(
+    State(state): State<AppState>,
+    Path(customer_id): Path<Uuid>,
+    user: TenantUser,
+    Json(request): Json<ImportOpenItemsRequest>,
+) -> Result<(StatusCode, Json<Value>), StatusCode> {
+    let TenantUser { tenant_context, user_id, .. } = user.staff_only()?;
+
+    match state
+        .credit_standing
+        .import(tenant_context.tenant_id.0, customer_id, request, user_id)
+        .await
+    {
+        Ok(import) => Ok((StatusCode::OK, Json(json!({ "success": true, "import": import })))),
+        Err(e) if matches!(e.code, ErrorCode::ValidationFailed | ErrorCode::ConflictError) => Ok((
+            error_status(&e),
+            Json(json!({ "success": false, "error": e.message })),
+        )),
+        Err(e) => {
+            tracing::error!("Failed to import open items of customer {}: {}", customer_id, e);
+            Err(error_status(&e))
+        }
+    }
+}
@@ -0,0 +1,200 @@
+//! Base currency and exchange rate handlers
+//!
+//! The base currency of the signed-in user's tenant and the exchange rates it
+//! entered, which the valuation and margin reports convert with. Rates a
+//! tenant has not entered fall back to the ECB reference rates where that job
+//! is enabled (see [`crate::exchange_rates`]).
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json,
+    routing::{get, post, put, Router},
+};
+use chrono::{NaiveDate, Utc};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::api_middleware::tenant_context::TenantUser;
+use crate::state::AppState;
+use erp_master_data::currency::ExchangeRateRequest;
+use erp_master_data::MasterDataError;
+
+#[derive(Debug, Deserialize)]
+pub struct BaseCurrencyRequest {
+    /// ISO 4217 code, e.g. `EUR`
+    pub base_currency: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListRatesParams {
+    /// Only rates converting from or into this currency
+    pub currency: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EffectiveRateParams {
+    pub from: String,
+    pub to: String,
+    /// Defaults to today (UTC)
+    pub date: Option<NaiveDate>,
+}
+
+/// Currency reads; they need an authenticated user
+pub fn currency_routes() -> Router<AppState> {
+    Router::new()
+        .route("/settings/currency", get(get_base_currency))
+        .route("/exchange-rates", get(list_rates))
+        .route("/exchange-rates/effective", get(effective_rate))
+        .route("/exchange-rates/:id", get(get_rate))
+}
+
+/// Currency changes; they need `settings:write`
+pub fn currency_admin_routes() -> Router<AppState> {
+    Router::new()
+        .route("/settings/currency", put(set_base_currency))
+        .route("/exchange-rates", post(create_rate))
+        .route("/exchange-rates/:id", put(update_rate).delete(delete_rate))
+}
+
+fn error_status(e: &MasterDataError) -> StatusCode {
+    match e {
+        MasterDataError::NotFoundError(_) => StatusCode::NOT_FOUND,
+        MasterDataError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+        MasterDataError::DuplicateExchangeRate { .. } => StatusCode::CONFLICT,
+        MasterDataError::ExchangeRateMissing { .. } => StatusCode::NOT_FOUND,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// Rejections the caller can act on are answered with their message
+fn error_response(e: MasterDataError, action: &str) -> Result<(StatusCode, Json<Value>), StatusCode> {
+    match error_status(&e) {
+        StatusCode::INTERNAL_SERVER_ERROR => {
+            tracing::error!("Failed to {}: {}", action, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+        status => Ok((status, Json(json!({ "success": false, "error": e.to_string() })))),
+    }
+}
+
+/// The currency reports are stated in
+async fn get_base_currency(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.exchange_rate_service(&tenant_context, &request_context);
+
+    match service.base_currency().await {
+        Ok(base_currency) => Ok(Json(json!({
+            "success": true,
+            "base_currency": base_currency,
+            "ecb_rates": state.config.exchange_rates.ecb_enabled
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to load base currency: {}", e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Change the currency reports are stated in
+async fn set_base_currency(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+    Json(request): Json<BaseCurrencyRequest>,
+) -> Result<(StatusCode, Json<Value>), StatusCode> {
+    let service = state.exchange_rate_service(&tenant_context, &request_context);
+
+    match service.set_base_currency(&request.base_currency).await {
+        Ok(base_currency) => Ok((StatusCode::OK, Json(json!({ "success": true, "base_currency": base_currency })))),
+        Err(e) => error_response(e, "set base currency"),
+    }
+}
+
+/// The tenant's own exchange rates, latest first
+async fn list_rates(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+    Query(params): Query<ListRatesParams>,
+) -> Result<(StatusCode, Json<Value>), StatusCode> {
+    let service = state.exchange_rate_service(&tenant_context, &request_context);
+
+    match service.list_rates(params.currency.as_deref()).await {
+        Ok(rates) => Ok((StatusCode::OK, Json(json!({ "success": true, "rates": rates })))),
+        Err(e) => error_response(e, "list exchange rates"),
+    }
+}
+
+/// The rate reports would apply for a pair on a date, and where it comes from
+async fn effective_rate(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+    Query(params): Query<EffectiveRateParams>,
+) -> Result<(StatusCode, Json<Value>), StatusCode> {
+    let service = state.exchange_rate_service(&tenant_context, &request_context);
+    let on = params.date.unwrap_or_else(|| Utc::now().date_naive());
+
+    match service.effective_rate(&params.from, &params.to, on).await {
+        Ok(rate) => Ok((StatusCode::OK, Json(json!({ "success": true, "rate": rate })))),
+        Err(e) => error_response(e, "look up exchange rate"),
+    }
+}
+
+async fn get_rate(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.exchange_rate_service(&tenant_context, &request_context);
+
+    match service.get_rate(id).await {
+        Ok(rate) => Ok(Json(json!({ "success": true, "rate": rate }))),
+        Err(e) => {
+            tracing::error!("Failed to get exchange rate {}: {}", id, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Enter a rate; a pair has at most one per day
+async fn create_rate(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+    Json(request): Json<ExchangeRateRequest>,
+) -> Result<(StatusCode, Json<Value>), StatusCode> {
+    let service = state.exchange_rate_service(&tenant_context, &request_context);
+
+    match service.create_rate(request).await {
+        Ok(rate) => Ok((StatusCode::CREATED, Json(json!({ "success": true, "rate": rate })))),
+        Err(e) => error_response(e, "create exchange rate"),
+    }
+}
+
+async fn update_rate(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+    Json(request): Json<ExchangeRateRequest>,
+) -> Result<(StatusCode, Json<Value>), StatusCode> {
+    let service = state.exchange_rate_service(&tenant_context, &request_context);
+
+    match service.update_rate(id, request).await {
+        Ok(rate) => Ok((StatusCode::OK, Json(json!({ "success": true, "rate": rate })))),
+        Err(e) => error_response(e, "update exchange rate"),
+    }
+}
+
+async fn delete_rate(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+) -> Result<(StatusCode, Json<Value>), StatusCode> {
+    let service = state.exchange_rate_service(&tenant_context, &request_context);
+
+    match service.delete_rate(id).await {
+        Ok(()) => Ok((StatusCode::OK, Json(json!({ "success": true, "id": id })))),
+        Err(e) => error_response(e, "delete exchange rate"),
+    }
+}
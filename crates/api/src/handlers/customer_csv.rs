@@ -0,0 +1,222 @@
+//! Customer CSV export and import handlers
+//!
+//! Customers go out and come back in the one layout of
+//! [`erp_master_data::customer::csv_schema`], so an exported file can be
+//! edited and imported again, also into another tenant. `mode=validate-only`
+//! reads a file without creating anything and reports every cell that would
+//! not survive the trip.
+
+use axum::{
+    extract::{Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Json, Response},
+    routing::{get, post, Router},
+};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::api_middleware::tenant_context::TenantUser;
+use crate::{letterhead::export_header, state::AppState};
+use erp_core::ErrorCode;
+use erp_master_data::customer::bulk_import::{BulkImportError, BulkImportOptions, BulkImportRow};
+use erp_master_data::customer::csv_schema::{CustomerCsvSchema, EXPORT_MAX_ROWS};
+use erp_master_data::customer::CustomerSearchCriteria;
+use erp_master_data::MasterDataError;
+
+#[derive(Debug, Deserialize)]
+pub struct CustomerExportParams {
+    /// Address groups per row; as many as the customers need if not given
+    pub max_addresses: Option<usize>,
+    /// Contact groups per row; as many as the customers need if not given
+    pub max_contacts: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CustomerImportMode {
+    #[default]
+    Create,
+    ValidateOnly,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CustomerImportParams {
+    #[serde(default)]
+    pub mode: CustomerImportMode,
+    /// Create every customer of the file or none of them
+    #[serde(default)]
+    pub atomic: bool,
+}
+
+/// Customer export; it belongs in the exports concurrency group and needs a user who may read customers
+pub fn customer_export_routes() -> Router<AppState> {
+    Router::new().route("/customers/export", get(export_customers))
+}
+
+/// Customer import; it needs a user who may change customers
+pub fn customer_import_routes() -> Router<AppState> {
+    Router::new().route("/customers/import", post(import_customers))
+}
+
+fn error_status(e: &MasterDataError) -> StatusCode {
+    match e {
+        MasterDataError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+        MasterDataError::DuplicateCustomerNumber { .. } => StatusCode::CONFLICT,
+        MasterDataError::Core(e) if e.code == ErrorCode::PlanLimitExceeded => StatusCode::FORBIDDEN,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// Every customer with its addresses and contacts, up to the export limit, as CSV below the export header
+async fn export_customers(
+    State(state): State<AppState>,
+    user: TenantUser,
+    Query(params): Query<CustomerExportParams>,
+) -> Result<Response, StatusCode> {
+    let TenantUser { tenant_context, request_context, .. } = user.staff_only()?;
+    let tenant_id = tenant_context.tenant_id.0;
+    let service = state.customer_service(tenant_context.clone());
+
+    let criteria = CustomerSearchCriteria {
+        page: Some(1),
+        page_size: Some(EXPORT_MAX_ROWS as u32 + 1),
+        ..Default::default()
+    };
+    let listed = service.search_customers(criteria).await.map_err(|e| {
+        tracing::error!("Failed to list customers of tenant {} for export: {}", tenant_id, e);
+        error_status(&e)
+    })?;
+    let truncated = listed.customers.len() > EXPORT_MAX_ROWS;
+
+    // The list leaves out addresses and contacts
+    let mut customers = Vec::with_capacity(listed.customers.len().min(EXPORT_MAX_ROWS));
+    for listed in listed.customers.into_iter().take(EXPORT_MAX_ROWS) {
+        match service.get_customer(listed.id).await {
+            Ok(Some(customer)) => customers.push(customer),
+            Ok(None) => {}
+            Err(e) => {
+                tracing::error!("Failed to load customer {} for export: {}", listed.id, e);
+                return Err(error_status(&e));
+            }
+        }
+    }
+
+    let fitting = CustomerCsvSchema::fitting(&customers);
+    let schema = CustomerCsvSchema::new(
+        params.max_addresses.unwrap_or(fitting.max_addresses),
+        params.max_contacts.unwrap_or(fitting.max_contacts),
+    )
+    .map_err(|e| error_status(&e))?;
+
+    let export = export_header(state.letterheads.as_ref(), tenant_id, "Customers", &request_context)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to load letterhead of tenant {}: {}", tenant_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .with_filter("max_addresses", schema.max_addresses)
+        .with_filter("max_contacts", schema.max_contacts)
+        .with_optional_filter("truncated_at", truncated.then_some(EXPORT_MAX_ROWS));
+    let csv = match schema.export(&export, &customers) {
+        Ok(csv) => csv,
+        Err(e) => return Ok((error_status(&e), Json(json!({ "success": false, "error": e.to_string() }))).into_response()),
+    };
+
+    let filename = format!("customers-{}-{}.csv", tenant_id, export.generated_at.format("%Y%m%d%H%M%S"));
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename)),
+        ],
+        csv,
+    )
+        .into_response())
+}
+
+/// Create a customer per row of a CSV file in the export layout; nothing is
+/// created unless every row reads cleanly, and with `atomic=true` unless
+/// every customer can be created
+async fn import_customers(
+    State(state): State<AppState>,
+    user: TenantUser,
+    Query(params): Query<CustomerImportParams>,
+    body: String,
+) -> Result<(StatusCode, Json<serde_json::Value>), StatusCode> {
+    let TenantUser { tenant_context, user_id, .. } = user.staff_only()?;
+    // Any file an export could have written is accepted
+    let schema = CustomerCsvSchema::widest();
+
+    if params.mode == CustomerImportMode::ValidateOnly {
+        let check = schema.validate_only(&body);
+        return Ok((
+            StatusCode::OK,
+            Json(json!({
+                "success": check.is_clean(),
+                "mode": "validate-only",
+                "valid_rows": check.customers.len(),
+                "errors": check.errors
+            })),
+        ));
+    }
+
+    let import = schema.import(&body);
+    if !import.is_clean() {
+        return Ok((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(json!({
+                "success": false,
+                "error": "The file has rows that cannot be read; nothing was imported",
+                "errors": import.errors
+            })),
+        ));
+    }
+
+    let service = state.customer_service(tenant_context.clone());
+    let options = BulkImportOptions {
+        batch_size: state.config.customer_import.batch_size,
+        atomic: params.atomic,
+    };
+    let (lines, requests): (Vec<_>, Vec<_>) = import.customers.into_iter().map(|row| (row.line, row.request)).unzip();
+    let result = match service.bulk_create_customers(requests, options, user_id).await {
+        Ok(result) => result,
+        Err(e) if error_status(&e) != StatusCode::INTERNAL_SERVER_ERROR => {
+            return Ok((error_status(&e), Json(json!({ "success": false, "error": e.to_string() }))));
+        }
+        Err(e) => {
+            tracing::error!("Failed to import customers of tenant {}: {}", tenant_context.tenant_id.0, e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    let mut created = Vec::new();
+    let mut failed = Vec::new();
+    for row in &result.rows {
+        let line = lines[row.index()];
+        match row {
+            BulkImportRow::Created { customer_id, customer_number, .. } => created.push(json!({
+                "line": line,
+                "id": customer_id,
+                "customer_number": customer_number
+            })),
+            BulkImportRow::Failed { error, .. } if error.is_internal() => {
+                tracing::error!("Failed to import customer from line {}: {}", line, error);
+                failed.push(json!({ "line": line, "message": "The customer could not be created" }))
+            }
+            BulkImportRow::Failed { error: BulkImportError::Conflict { duplicate_of: Some(first), .. }, .. } => {
+                failed.push(json!({ "line": line, "message": format!("The customer number is already used on line {}", lines[*first]) }))
+            }
+            BulkImportRow::Failed { error, .. } => failed.push(json!({ "line": line, "message": error.to_string() })),
+        }
+    }
+
+    Ok((
+        if result.aborted { StatusCode::UNPROCESSABLE_ENTITY } else { StatusCode::OK },
+        Json(json!({
+            "success": result.is_clean(),
+            "mode": "create",
+            "atomic": params.atomic,
+            "created": created,
+            "errors": failed
+        })),
+    ))
+}
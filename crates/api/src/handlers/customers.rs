@@ -4,16 +4,25 @@
 
 use axum::{
     extract::{State, Path, Query, Extension},
-    http::StatusCode,
-    response::Json,
+    http::{header, StatusCode},
+    response::{IntoResponse, Json, Response},
     routing::{get, post, put, delete, Router},
 };
+use std::sync::Arc;
 use serde::Deserialize;
+use std::collections::HashMap;
 use serde_json::{json, Value};
 use uuid::Uuid;
 
-use crate::state::AppState;
-use erp_core::TenantContext;
+use crate::{
+    api_middleware::api_version::ApiVersion,
+    handlers::pagination::PaginatedResponse,
+    idempotency::{idempotency_middleware, Idempotency, CUSTOMERS_SCOPE},
+    responses::customer_response,
+    state::AppState,
+};
+use erp_core::{portal::PortalScope, ActorContext, ErrorCode, RequestContext, TenantContext};
+use erp_master_data::MasterDataError;
 use erp_master_data::customer::model::{
     CreateCustomerRequest as DomainCreateCustomerRequest,
     UpdateCustomerRequest as DomainUpdateCustomerRequest,
@@ -50,6 +59,8 @@ pub struct CreateCustomerRequest {
     pub status: Option<EntityStatus>,
     pub credit_status: Option<CreditStatus>,
     pub acquisition_channel: Option<AcquisitionChannel>,
+    /// Tax numbers by kind (`vat`, `ein`, ...); ones that cannot be verified are kept with a warning
+    pub tax_numbers: Option<HashMap<String, String>>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -70,31 +81,160 @@ pub struct CustomerSearchParams {
     pub customer_type: Option<CustomerType>,
     pub status: Option<EntityStatus>,
     pub lifecycle_stage: Option<CustomerLifecycleStage>,
+    /// `next_cursor` of the previous page; continues after it instead of using `page`
+    pub cursor: Option<String>,
+}
+
+/// Exact-match lookup on an encrypted identifier; exactly one must be given
+#[derive(Debug, Deserialize)]
+pub struct CustomerLookupParams {
+    pub tax_number: Option<String>,
+    pub iban: Option<String>,
 }
 
 
 /// Create customer management routes
-pub fn customer_routes() -> Router<AppState> {
+pub fn customer_routes(idempotency: &Arc<Idempotency>) -> Router<AppState> {
     Router::new()
         .route("/", get(list_customers))
-        .route("/", post(create_customer))
+        .route(
+            "/",
+            post(create_customer).layer(axum::middleware::from_fn_with_state(
+                idempotency.endpoint(CUSTOMERS_SCOPE),
+                idempotency_middleware,
+            )),
+        )
+        .route("/lookup", get(lookup_customers))
         .route("/:id", get(get_customer))
         .route("/:id", put(update_customer))
         .route("/:id", delete(delete_customer))
         .route("/:id/hierarchy", get(get_customer_hierarchy))
+        .route("/:id/financial-identifiers", get(get_masked_financial_identifiers))
+}
+
+/// Portal scope of the request, see [`erp_core::portal`]; a portal token
+/// only works for the tenant it was issued in
+fn portal_scope(
+    tenant_context: &TenantContext,
+    request_context: &Option<RequestContext>,
+) -> Result<Option<PortalScope>, StatusCode> {
+    let Some(context) = request_context else {
+        return Ok(None);
+    };
+    let Some(scope) = context.portal else {
+        return Ok(None);
+    };
+    let token_tenant = context.tenant_context.as_ref().map(|t| t.tenant_id);
+    if token_tenant != Some(tenant_context.tenant_id) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    Ok(Some(scope))
+}
+
+/// Find customers by tax number or IBAN
+async fn lookup_customers(
+    State(state): State<AppState>,
+    Query(params): Query<CustomerLookupParams>,
+    Extension(tenant_context): Extension<TenantContext>,
+    Extension(version): Extension<ApiVersion>,
+    request_context: Option<RequestContext>,
+) -> Result<Json<Value>, StatusCode> {
+    let scope = portal_scope(&tenant_context, &request_context)?;
+    let repository = state.scoped_customer_repository(tenant_context, scope);
+
+    let result = match (params.tax_number, params.iban) {
+        (Some(tax_number), None) => repository.find_customers_by_tax_number(&tax_number).await,
+        (None, Some(iban)) => repository.find_customers_by_iban(&iban).await,
+        _ => return Err(StatusCode::BAD_REQUEST),
+    };
+
+    match result {
+        Ok(customers) => Ok(Json(json!({
+            "success": true,
+            "customers": customers
+                .into_iter()
+                .map(|customer| customer_response(version, customer))
+                .collect::<Result<Vec<_>, _>>()?
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to look up customers: {}", e);
+            Ok(Json(json!({
+                "success": false,
+                "error": "Failed to look up customers",
+                "message": e.to_string()
+            })))
+        }
+    }
+}
+
+/// Tax numbers and bank accounts of a customer, masked
+async fn get_masked_financial_identifiers(
+    State(state): State<AppState>,
+    Path(customer_id): Path<Uuid>,
+    Extension(tenant_context): Extension<TenantContext>,
+) -> Result<Json<Value>, StatusCode> {
+    let repository = state.customer_repository(tenant_context);
+
+    match repository.get_masked_financial_identifiers(customer_id).await {
+        Ok(Some(identifiers)) => Ok(Json(json!({
+            "success": true,
+            "financial_identifiers": identifiers
+        }))),
+        Ok(None) => Ok(Json(json!({
+            "success": false,
+            "error": "Customer not found",
+            "message": format!("Customer with ID {} not found", customer_id)
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to get financial identifiers of customer {}: {}", customer_id, e);
+            Ok(Json(json!({
+                "success": false,
+                "error": "Failed to retrieve financial identifiers",
+                "message": e.to_string()
+            })))
+        }
+    }
 }
 
 /// List all customers
-async fn list_customers(
+///
+/// Paged by `page` and `limit`, or by `cursor`: every page but the last
+/// carries a `next_cursor` to pass back for the next one.
+#[utoipa::path(
+    get,
+    path = "/api/v1/customers",
+    params(
+        ("X-Tenant-Id" = String, Header, description = "Tenant ID"),
+        ("page" = Option<u32>, Query, description = "1-based page, defaults to 1"),
+        ("limit" = Option<u32>, Query, description = "Customers per page, defaults to 20"),
+        ("cursor" = Option<String>, Query, description = "`next_cursor` of the previous page; continues after it instead of using `page`"),
+        ("legal_name" = Option<String>, Query, description = "Search term over the legal name"),
+        ("customer_number" = Option<String>, Query, description = "Only this customer number"),
+        ("customer_type" = Option<CustomerType>, Query, description = "Only customers of this type"),
+        ("status" = Option<EntityStatus>, Query, description = "Only customers in this status"),
+        ("lifecycle_stage" = Option<CustomerLifecycleStage>, Query, description = "Only customers in this lifecycle stage")
+    ),
+    responses(
+        (status = 200, description = "One page of customers, shaped by the requested API version",
+            body = PaginatedResponse<Value>),
+        (status = 400, description = "Invalid cursor"),
+        (status = 403, description = "Portal token of another tenant"),
+    ),
+    tag = "customers"
+)]
+pub async fn list_customers(
     State(state): State<AppState>,
     Query(pagination): Query<PaginationParams>,
     Query(search): Query<CustomerSearchParams>,
     Extension(tenant_context): Extension<TenantContext>,
-) -> Result<Json<Value>, StatusCode> {
-    // Use tenant context from middleware
+    Extension(version): Extension<ApiVersion>,
+    request_context: Option<RequestContext>,
+) -> Result<Response, StatusCode> {
+    // Use tenant context from middleware; portal accounts only list their customers
+    let scope = portal_scope(&tenant_context, &request_context)?;
 
     // Create service instance with business logic
-    let service = state.customer_service(tenant_context.clone());
+    let service = state.scoped_customer_service(tenant_context.clone(), scope);
 
     // Build search criteria
     let criteria = CustomerSearchCriteria {
@@ -105,49 +245,78 @@ async fn list_customers(
         lifecycle_stages: search.lifecycle_stage.map(|ls| vec![ls]),
         page: Some(pagination.page),
         page_size: Some(pagination.limit),
+        cursor: search.cursor,
         ..Default::default()
     };
 
     // Call service with business rules applied
     match service.search_customers(criteria).await {
         Ok(search_response) => {
-            Ok(Json(json!({
-                "success": true,
-                "customers": search_response.customers,
-                "pagination": {
-                    "page": search_response.page,
-                    "limit": search_response.page_size,
-                    "total": search_response.total_count,
-                    "total_pages": search_response.total_pages
-                },
-                "tenant_id": tenant_context.tenant_id.0
-            })))
+            let customers = search_response.customers
+                .into_iter()
+                .map(|customer| customer_response(version, customer))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(PaginatedResponse::new(
+                customers,
+                search_response.total_count,
+                search_response.page,
+                search_response.page_size,
+            )
+            .with_next_cursor(search_response.next_cursor)
+            .into_response())
         },
+        Err(MasterDataError::ValidationError { field, .. }) if field == "cursor" => Err(StatusCode::BAD_REQUEST),
         Err(e) => {
             tracing::error!("Failed to list customers: {}", e);
             Ok(Json(json!({
                 "success": false,
                 "error": "Failed to retrieve customers",
                 "message": e.to_string()
-            })))
+            })).into_response())
         }
     }
 }
 
 /// Create a new customer
-async fn create_customer(
+///
+/// Answered with `201 Created` and the customer's `Location`. A retry sent
+/// with the same `Idempotency-Key` and body gets that response again instead
+/// of creating a second customer.
+#[utoipa::path(
+    post,
+    path = "/api/v1/customers",
+    request_body = Object,
+    params(
+        ("X-Tenant-Id" = String, Header, description = "Tenant ID"),
+        ("Idempotency-Key" = Option<String>, Header, description = "1 to 255 visible ASCII characters; \
+            a retry with the same key and body gets the first response, kept for 24 hours")
+    ),
+    responses(
+        (status = 201, description = "Customer created; replays carry `Idempotent-Replayed: true`", body = Object,
+            headers(("Location" = String, description = "URL of the created customer"))),
+        (status = 400, description = "Invalid Idempotency-Key"),
+        (status = 401, description = "No signed-in user to record as the creator"),
+        (status = 403, description = "Customer limit of the plan reached"),
+        (status = 409, description = "Idempotency-Key used for a different request, or its request still runs"),
+    ),
+    tag = "customers"
+)]
+pub async fn create_customer(
     State(state): State<AppState>,
     Extension(tenant_context): Extension<TenantContext>,
+    Extension(version): Extension<ApiVersion>,
+    actor: ActorContext,
+    request_context: Option<RequestContext>,
     Json(payload): Json<CreateCustomerRequest>,
-) -> Result<Json<Value>, StatusCode> {
+) -> Result<Response, StatusCode> {
     // Use tenant context from middleware
 
     // Basic validation
     if payload.legal_name.trim().is_empty() {
-        return Ok(Json(json!({
+        return Ok((StatusCode::OK, Json(json!({
             "success": false,
             "error": "Legal name is required"
-        })));
+        }))).into_response());
     }
 
     // Create service instance with business logic
@@ -172,7 +341,7 @@ async fn create_customer(
         addresses: None,
         contacts: None,
         tax_jurisdictions: None,
-        tax_numbers: None,
+        tax_numbers: payload.tax_numbers,
         financial_info: None,
         sales_representative_id: None,
         account_manager_id: None,
@@ -180,25 +349,45 @@ async fn create_customer(
         sync_info: None,
     };
 
-    // Use a default user ID for created_by (this would come from JWT in production)
-    let created_by = uuid::Uuid::new_v4();
+    let created_by = actor.id().map_err(|_| StatusCode::UNAUTHORIZED)?;
 
     // Call service with business rules applied
     match service.create_customer(domain_request, created_by).await {
-        Ok(customer) => {
-            Ok(Json(json!({
+        Ok(mut customer) => {
+            // The territories decide who looks after the new customer
+            let request_context = request_context.unwrap_or_else(RequestContext::new);
+            match state.territory_service(&tenant_context, &request_context).assign_customer(customer.id).await {
+                Ok(assignment) => {
+                    customer.sales_representative_id = assignment.sales_representative.assignee_id;
+                    customer.account_manager_id = assignment.account_manager.assignee_id;
+                    customer.assignment = assignment;
+                }
+                Err(e) => tracing::warn!("Failed to assign customer {} to a territory: {}", customer.id, e),
+            }
+
+            let location = format!("/api/v1/customers/{}", customer.id);
+            Ok((StatusCode::CREATED, [(header::LOCATION, location)], Json(json!({
                 "success": true,
-                "customer": customer,
+                "customer": customer_response(version, customer)?,
                 "message": "Customer created successfully"
-            })))
+            }))).into_response())
         },
+        // The tenant is at the customer limit of its plan
+        Err(MasterDataError::Core(e)) if e.code == ErrorCode::PlanLimitExceeded => {
+            Ok((StatusCode::FORBIDDEN, Json(json!({
+                "success": false,
+                "error": "Customer limit of the plan reached",
+                "code": e.code,
+                "message": e.message
+            }))).into_response())
+        }
         Err(e) => {
             tracing::error!("Failed to create customer: {}", e);
-            Ok(Json(json!({
+            Ok((StatusCode::OK, Json(json!({
                 "success": false,
                 "error": "Failed to create customer",
                 "message": e.to_string()
-            })))
+            }))).into_response())
         }
     }
 }
@@ -208,18 +397,21 @@ async fn get_customer(
     State(state): State<AppState>,
     Path(customer_id): Path<Uuid>,
     Extension(tenant_context): Extension<TenantContext>,
+    Extension(version): Extension<ApiVersion>,
+    request_context: Option<RequestContext>,
 ) -> Result<Json<Value>, StatusCode> {
-    // Use tenant context from middleware
+    // Use tenant context from middleware; customers outside a portal scope are not found
+    let scope = portal_scope(&tenant_context, &request_context)?;
 
     // Create service instance with business logic
-    let service = state.customer_service(tenant_context.clone());
+    let service = state.scoped_customer_service(tenant_context.clone(), scope);
 
     // Call service with business rules applied
     match service.get_customer(customer_id).await {
         Ok(Some(customer)) => {
             Ok(Json(json!({
                 "success": true,
-                "customer": customer
+                "customer": customer_response(version, customer)?
             })))
         },
         Ok(None) => {
@@ -244,7 +436,9 @@ async fn get_customer(
 async fn update_customer(
     State(state): State<AppState>,
     Extension(tenant_context): Extension<TenantContext>,
+    Extension(version): Extension<ApiVersion>,
     Path(customer_id): Path<Uuid>,
+    actor: ActorContext,
     Json(payload): Json<UpdateCustomerRequest>,
 ) -> Result<Json<Value>, StatusCode> {
     // Use tenant context from middleware
@@ -274,15 +468,14 @@ async fn update_customer(
         version: 1, // Version for optimistic locking - in production this would come from the request
     };
 
-    // Use a default user ID for modified_by (this would come from JWT in production)
-    let modified_by = uuid::Uuid::new_v4();
+    let modified_by = actor.id().map_err(|_| StatusCode::UNAUTHORIZED)?;
 
     // Call service with business rules applied
     match service.update_customer(customer_id, domain_update, modified_by).await {
         Ok(customer) => {
             Ok(Json(json!({
                 "success": true,
-                "customer": customer,
+                "customer": customer_response(version, customer)?,
                 "message": "Customer updated successfully"
             })))
         },
@@ -302,14 +495,14 @@ async fn delete_customer(
     State(state): State<AppState>,
     Path(customer_id): Path<Uuid>,
     Extension(tenant_context): Extension<TenantContext>,
+    actor: ActorContext,
 ) -> Result<Json<Value>, StatusCode> {
     // Use tenant context from middleware
 
     // Create service instance with business logic
     let service = state.customer_service(tenant_context.clone());
 
-    // Use a default user ID for deleted_by (this would come from JWT in production)
-    let deleted_by = uuid::Uuid::new_v4();
+    let deleted_by = actor.id().map_err(|_| StatusCode::UNAUTHORIZED)?;
 
     // Call service with business rules applied (soft delete)
     match service.delete_customer(customer_id, deleted_by).await {
@@ -335,11 +528,13 @@ async fn get_customer_hierarchy(
     State(state): State<AppState>,
     Path(customer_id): Path<Uuid>,
     Extension(tenant_context): Extension<TenantContext>,
+    request_context: Option<RequestContext>,
 ) -> Result<Json<Value>, StatusCode> {
-    // Use tenant context from middleware
+    // Use tenant context from middleware; portal accounts see their part of the hierarchy
+    let scope = portal_scope(&tenant_context, &request_context)?;
 
     // Create repository instance (hierarchy not yet in service layer)
-    let repository = state.customer_repository(tenant_context.clone());
+    let repository = state.scoped_customer_repository(tenant_context.clone(), scope);
 
     // Call repository to get customer hierarchy
     match repository.get_customer_hierarchy(customer_id).await {
@@ -0,0 +1,176 @@
+//! Email template handlers
+//!
+//! The named email templates with their variable schemas, and the signed-in
+//! user's tenant's overrides of them per locale. Overrides are validated
+//! against the schema when saved and can be previewed with sample values;
+//! see [`erp_auth::email::overrides`]. They need `settings:write`.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json,
+    routing::{get, post, put, Router},
+};
+use chrono::Utc;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::api_middleware::tenant_context::TenantUser;
+use crate::state::AppState;
+use erp_auth::email::{
+    built_in, normalize_locale, validate_override, NamedTemplate, ResolvedEmail, TemplateContent, TemplateOverride,
+    TemplateSource, BUILT_IN_TEMPLATES,
+};
+
+#[derive(Debug, Deserialize)]
+pub struct LocaleParams {
+    /// No locale is the tenant default
+    pub locale: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SaveOverrideRequest {
+    pub locale: Option<String>,
+    #[serde(flatten)]
+    pub content: TemplateContent,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PreviewRequest {
+    pub locale: Option<String>,
+    /// Unsaved wording to preview; without it the wording the chain picks is used
+    pub content: Option<TemplateContent>,
+}
+
+/// Email templates and their overrides; they need `settings:write`
+pub fn email_template_routes() -> Router<AppState> {
+    Router::new()
+        .route("/email-templates", get(list_templates))
+        .route("/email-templates/:name/overrides", put(save_override).delete(delete_override))
+        .route("/email-templates/:name/preview", post(preview_template))
+}
+
+fn template(name: &str) -> Result<&'static NamedTemplate, StatusCode> {
+    built_in(name).ok_or(StatusCode::NOT_FOUND)
+}
+
+/// `None` stays the tenant default; anything that is no locale is a bad request
+fn locale(locale: Option<&str>) -> Result<Option<String>, StatusCode> {
+    match locale.map(str::trim).filter(|l| !l.is_empty()) {
+        Some(locale) => normalize_locale(locale).map(Some).ok_or(StatusCode::BAD_REQUEST),
+        None => Ok(None),
+    }
+}
+
+/// Every named template with its variables and built-in wording, and the tenant's overrides
+async fn list_templates(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, .. }: TenantUser,
+) -> Result<Json<Value>, StatusCode> {
+
+    let templates = state.auth_service.email_templates();
+    match templates.store().list(tenant_context.tenant_id.0).await {
+        Ok(overrides) => Ok(Json(json!({
+            "success": true,
+            "templates": BUILT_IN_TEMPLATES,
+            "overrides": overrides
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to list email template overrides: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Store the tenant's wording of a template for a locale, or as its default
+async fn save_override(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+    Json(request): Json<SaveOverrideRequest>,
+) -> Result<(StatusCode, Json<Value>), StatusCode> {
+    let template = template(&name)?;
+    let locale = locale(request.locale.as_deref())?;
+
+    let issues = validate_override(template, &request.content);
+    if !issues.is_empty() {
+        return Ok((StatusCode::UNPROCESSABLE_ENTITY, Json(json!({ "success": false, "issues": issues }))));
+    }
+
+    let saved = TemplateOverride {
+        template_name: template.name.to_string(),
+        locale,
+        content: request.content,
+        updated_by: request_context.user_id,
+        updated_at: Utc::now(),
+    };
+    let templates = state.auth_service.email_templates();
+    match templates.store().save(tenant_context.tenant_id.0, &saved).await {
+        Ok(()) => Ok((StatusCode::OK, Json(json!({ "success": true, "override": saved })))),
+        Err(e) => {
+            tracing::error!("Failed to save email template override {}: {}", name, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Remove the tenant's wording of a template for a locale; emails fall back along the chain
+async fn delete_override(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    TenantUser { tenant_context, .. }: TenantUser,
+    Query(params): Query<LocaleParams>,
+) -> Result<Json<Value>, StatusCode> {
+    let template = template(&name)?;
+    let locale = locale(params.locale.as_deref())?;
+
+    let templates = state.auth_service.email_templates();
+    match templates.store().delete(tenant_context.tenant_id.0, template.name, locale.as_deref()).await {
+        Ok(true) => Ok(Json(json!({ "success": true }))),
+        Ok(false) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            tracing::error!("Failed to delete email template override {}: {}", name, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Render a template with sample values: the given wording, or the one the chain picks for the locale
+async fn preview_template(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    TenantUser { tenant_context, .. }: TenantUser,
+    Json(request): Json<PreviewRequest>,
+) -> Result<(StatusCode, Json<Value>), StatusCode> {
+    let template = template(&name)?;
+    let locale = locale(request.locale.as_deref())?;
+    let values = template.sample_values();
+
+    let rendered = match request.content {
+        Some(content) => {
+            let issues = validate_override(template, &content);
+            if !issues.is_empty() {
+                return Ok((StatusCode::UNPROCESSABLE_ENTITY, Json(json!({ "success": false, "issues": issues }))));
+            }
+            let source = if locale.is_some() { TemplateSource::TenantLocale } else { TemplateSource::TenantDefault };
+            content.render(&values).map(|email| ResolvedEmail {
+                source,
+                locale,
+                email,
+            })
+        }
+        None => {
+            let templates = state.auth_service.email_templates();
+            templates
+                .render_named(tenant_context.tenant_id.0, template, locale.as_deref(), &values)
+                .await
+        }
+    };
+    match rendered {
+        Ok(preview) => Ok((StatusCode::OK, Json(json!({ "success": true, "preview": preview, "values": values })))),
+        Err(e) => {
+            tracing::error!("Failed to preview email template {}: {}", name, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
@@ -0,0 +1,110 @@
+//! Existence check handlers
+//!
+//! Whether an email, SKU or customer number is taken in the tenant, for
+//! validating forms as the user types. Only `{"exists": bool}` comes back.
+//! See [`crate::existence_checks`] for the rate limit, the uniform timing of
+//! email checks and caching.
+
+use axum::{
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{Json, Response},
+    routing::{get, Router},
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::api_middleware::tenant_context::TenantUser;
+use crate::state::AppState;
+use erp_core::Error;
+
+#[derive(Debug, Deserialize)]
+pub struct EmailParams {
+    pub email: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SkuParams {
+    pub sku: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CustomerNumberParams {
+    pub customer_number: String,
+}
+
+/// Existence checks; each needs read access to what it checks
+pub fn existence_routes() -> Router<AppState> {
+    Router::new()
+        .route(
+            "/users/exists",
+            get(user_email_exists).layer(axum::middleware::from_fn(erp_auth::require_permission("users:read"))),
+        )
+        .route(
+            "/products/exists",
+            get(product_sku_exists).layer(axum::middleware::from_fn(erp_auth::require_permission("products:read"))),
+        )
+        .route(
+            "/customers/exists",
+            get(customer_number_exists).layer(axum::middleware::from_fn(erp_auth::require_permission("customers:read"))),
+        )
+}
+
+fn failure(e: Error, action: &str) -> (StatusCode, Json<Value>) {
+    tracing::error!("Failed to {}: {}", action, e);
+    (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "success": false, "error": e.message })))
+}
+
+fn required<'a>(value: &'a str, name: &str) -> Result<&'a str, (StatusCode, Json<Value>)> {
+    if value.trim().is_empty() {
+        return Err((StatusCode::BAD_REQUEST, Json(json!({ "success": false, "error": format!("{} is required", name) }))));
+    }
+    Ok(value)
+}
+
+/// Whether a user of the tenant has the email; never cached
+async fn user_email_exists(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, .. }: TenantUser,
+    Query(params): Query<EmailParams>,
+) -> Result<Response, (StatusCode, Json<Value>)> {
+    let email = required(&params.email, "email")?;
+    let exists = state
+        .existence_checks
+        .email_exists(&tenant_context, email)
+        .await
+        .map_err(|e| failure(e, "check user email"))?;
+    Ok(state.existence_checks.uncached_answer(exists))
+}
+
+/// Whether a product of the tenant has the SKU
+async fn product_sku_exists(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, .. }: TenantUser,
+    headers: HeaderMap,
+    Query(params): Query<SkuParams>,
+) -> Result<Response, (StatusCode, Json<Value>)> {
+    let sku = required(&params.sku, "sku")?;
+    let exists = state
+        .existence_checks
+        .sku_exists(tenant_context.tenant_id.0, sku)
+        .await
+        .map_err(|e| failure(e, "check product SKU"))?;
+    Ok(state.existence_checks.cached_answer(exists, &headers))
+}
+
+/// Whether a customer of the tenant has the number
+async fn customer_number_exists(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, .. }: TenantUser,
+    headers: HeaderMap,
+    Query(params): Query<CustomerNumberParams>,
+) -> Result<Response, (StatusCode, Json<Value>)> {
+    let customer_number = required(&params.customer_number, "customer_number")?;
+    let exists = state
+        .existence_checks
+        .customer_number_exists(tenant_context.tenant_id.0, customer_number)
+        .await
+        .map_err(|e| failure(e, "check customer number"))?;
+    Ok(state.existence_checks.cached_answer(exists, &headers))
+}
@@ -0,0 +1,428 @@
+//! Inventory handlers
+//!
+//! Serial number lookups for serialized products, inventory optimization
+//! reports with per-recommendation explanations, what-if forecast scenarios
+//! for promotions, physical stocktake imports from scanner files, and the
+//! reserved quantity drift report.
+
+use axum::{
+    extract::{DefaultBodyLimit, Extension, Path, Query, State},
+    http::StatusCode,
+    response::Json,
+    routing::{get, post, Router},
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::api_middleware::tenant_context::TenantUser;
+use crate::state::AppState;
+use erp_core::{ActorContext, ErrorCode, TenantContext};
+use erp_master_data::inventory::{
+    CreateStocktakeSessionRequest, ForecastScenarioRequest, InventoryOptimizationReport, OptimizationParameters,
+    SerialStatus, };
+use erp_master_data::MasterDataError;
+
+#[derive(Debug, Deserialize)]
+pub struct SerialListParams {
+    pub status: Option<SerialStatus>,
+    pub location_id: Option<Uuid>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StocktakeImportParams {
+    pub session_id: Uuid,
+    /// Uploading the same file name again replaces that file's lines
+    pub file_name: String,
+}
+
+/// Scanner files of a large warehouse exceed the default request body limit
+const STOCKTAKE_MAX_FILE_BYTES: usize = 32 * 1024 * 1024;
+
+/// Create inventory routes
+pub fn inventory_routes() -> Router<AppState> {
+    Router::new()
+        .route("/serials/:serial", get(get_serial_history))
+        .route("/products/:product_id/serials", get(list_product_serials))
+        .route("/locations/:location_id/optimization-reports", post(create_optimization_report))
+        .route("/optimization-reports/:report_id", get(get_optimization_report))
+        .route("/forecast/scenario", post(run_forecast_scenario))
+        .route("/forecast/scenarios", get(list_forecast_scenarios))
+        .route("/forecast/scenarios/:id", get(get_forecast_scenario))
+        .route("/forecast/scenarios/:id/comparison", get(compare_forecast_scenario))
+}
+
+/// Create stocktake routes; they need an authenticated user
+pub fn stocktake_routes() -> Router<AppState> {
+    Router::new()
+        .route("/sessions", post(create_stocktake_session))
+        .route("/import", post(import_stocktake_file).layer(DefaultBodyLimit::max(STOCKTAKE_MAX_FILE_BYTES)))
+        .route("/sessions/:id", get(get_stocktake_session))
+        .route("/sessions/:id/submit", post(submit_stocktake_session))
+        .route("/sessions/:id/summary", get(get_stocktake_summary))
+}
+
+/// Create reserved quantity drift routes; they need an authenticated user,
+/// and repairs `inventory:write`
+pub fn reservation_drift_routes() -> Router<AppState> {
+    Router::new()
+        .route("/", get(get_reservation_drift))
+        .route(
+            "/:location_item_id/repair",
+            post(repair_reservation_drift).layer(axum::middleware::from_fn(erp_auth::require_permission("inventory:write"))),
+        )
+}
+
+fn error_status(e: &MasterDataError) -> StatusCode {
+    match e {
+        MasterDataError::SerialNotFound { .. } | MasterDataError::ProductNotFound { .. } => StatusCode::NOT_FOUND,
+        MasterDataError::NotFoundError(_) => StatusCode::NOT_FOUND,
+        MasterDataError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// Current state and full history of a serial number
+async fn get_serial_history(
+    State(state): State<AppState>,
+    Path(serial): Path<String>,
+    Extension(tenant_context): Extension<TenantContext>,
+    actor: Option<Extension<ActorContext>>,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.serial_tracking_service(&tenant_context, actor.map(|Extension(actor)| actor).unwrap_or_default());
+
+    match service.get_serial_history(&serial).await {
+        Ok(history) => Ok(Json(json!({
+            "success": true,
+            "serial": history.unit,
+            "history": history.events
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to get serial history for {}: {}", serial, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Serial units of a product, optionally filtered by status and location
+async fn list_product_serials(
+    State(state): State<AppState>,
+    Path(product_id): Path<Uuid>,
+    Query(params): Query<SerialListParams>,
+    Extension(tenant_context): Extension<TenantContext>,
+    actor: Option<Extension<ActorContext>>,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.serial_tracking_service(&tenant_context, actor.map(|Extension(actor)| actor).unwrap_or_default());
+
+    match service.list_product_serials(product_id, params.location_id, params.status).await {
+        Ok(serials) => Ok(Json(json!({
+            "success": true,
+            "product_id": product_id,
+            "count": serials.len(),
+            "serials": serials
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to list serials for product {}: {}", product_id, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Report body with a UI summary next to each recommendation
+fn report_response(report: &InventoryOptimizationReport) -> Value {
+    let summaries: Vec<Value> = report
+        .optimization_results
+        .iter()
+        .map(|result| json!({
+            "product_id": result.product_id,
+            "summary": result.explanation.summary()
+        }))
+        .collect();
+
+    json!({
+        "success": true,
+        "report": report,
+        "summaries": summaries
+    })
+}
+
+/// Optimize a location's items and store the report; parameters default when no body is sent
+async fn create_optimization_report(
+    State(state): State<AppState>,
+    Path(location_id): Path<Uuid>,
+    Extension(tenant_context): Extension<TenantContext>,
+    parameters: Option<Json<OptimizationParameters>>,
+) -> Result<Json<Value>, StatusCode> {
+    let parameters = parameters.map(|Json(p)| p).unwrap_or_default();
+    let engine = state.inventory_optimization_engine();
+
+    let report = match engine.optimize_location_items(location_id, &parameters).await {
+        Ok(report) => report,
+        Err(e) => {
+            tracing::error!("Failed to optimize location {}: {}", location_id, e);
+            return Err(error_status(&e));
+        }
+    };
+
+    let repository = state.optimization_report_repository();
+    if let Err(e) = repository.save_report(tenant_context.tenant_id.0, &report).await {
+        tracing::error!("Failed to store optimization report {}: {}", report.id, e);
+        return Err(error_status(&e));
+    }
+
+    Ok(Json(report_response(&report)))
+}
+
+/// A stored optimization report with its explanations
+async fn get_optimization_report(
+    State(state): State<AppState>,
+    Path(report_id): Path<Uuid>,
+    Extension(tenant_context): Extension<TenantContext>,
+) -> Result<Json<Value>, StatusCode> {
+    let repository = state.optimization_report_repository();
+
+    match repository.get_report(tenant_context.tenant_id.0, report_id).await {
+        Ok(Some(report)) => Ok(Json(report_response(&report))),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            tracing::error!("Failed to load optimization report {}: {}", report_id, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Project stock under the given demand uplifts; stored when `save` is set
+async fn run_forecast_scenario(
+    State(state): State<AppState>,
+    Extension(tenant_context): Extension<TenantContext>,
+    actor: Option<Extension<ActorContext>>,
+    Json(request): Json<ForecastScenarioRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    let actor = actor.map(|Extension(actor)| actor).unwrap_or_default();
+    let service = state.forecast_scenario_service(&tenant_context, actor);
+    let created_by = actor.user_id;
+    let saved = request.save;
+
+    match service.run_scenario(request, created_by).await {
+        Ok(scenario) => Ok(Json(json!({
+            "success": true,
+            "saved": saved,
+            "scenario": scenario
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to run forecast scenario: {}", e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Saved scenarios of the tenant, newest first
+async fn list_forecast_scenarios(
+    State(state): State<AppState>,
+    Extension(tenant_context): Extension<TenantContext>,
+    actor: Option<Extension<ActorContext>>,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.forecast_scenario_service(&tenant_context, actor.map(|Extension(actor)| actor).unwrap_or_default());
+
+    match service.list_scenarios().await {
+        Ok(scenarios) => Ok(Json(json!({
+            "success": true,
+            "count": scenarios.len(),
+            "scenarios": scenarios
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to list forecast scenarios: {}", e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+async fn get_forecast_scenario(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Extension(tenant_context): Extension<TenantContext>,
+    actor: Option<Extension<ActorContext>>,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.forecast_scenario_service(&tenant_context, actor.map(|Extension(actor)| actor).unwrap_or_default());
+
+    match service.get_scenario(id).await {
+        Ok(scenario) => Ok(Json(json!({
+            "success": true,
+            "scenario": scenario
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to load forecast scenario {}: {}", id, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// A saved scenario's projected demand next to the actual demand so far
+async fn compare_forecast_scenario(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Extension(tenant_context): Extension<TenantContext>,
+    actor: Option<Extension<ActorContext>>,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.forecast_scenario_service(&tenant_context, actor.map(|Extension(actor)| actor).unwrap_or_default());
+
+    match service.compare_with_actuals(id).await {
+        Ok(comparison) => Ok(Json(json!({
+            "success": true,
+            "comparison": comparison
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to compare forecast scenario {}: {}", id, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Open a stocktake session; variances are computed against stock at `snapshot_at`
+async fn create_stocktake_session(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+    Json(request): Json<CreateStocktakeSessionRequest>,
+) -> Result<(StatusCode, Json<Value>), StatusCode> {
+    let service = state.stocktake_service(&tenant_context, &request_context);
+    let user_id = request_context.user_id.ok_or(StatusCode::FORBIDDEN)?;
+
+    match service.create_session(request, user_id).await {
+        Ok(session) => Ok((StatusCode::CREATED, Json(json!({
+            "success": true,
+            "session": session
+        })))),
+        Err(e) => {
+            tracing::error!("Failed to create stocktake session: {}", e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Import a scanner CSV sent as the request body into an open session
+async fn import_stocktake_file(
+    State(state): State<AppState>,
+    Query(params): Query<StocktakeImportParams>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+    body: String,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.stocktake_service(&tenant_context, &request_context);
+
+    match service.import_file(params.session_id, &params.file_name, &body).await {
+        Ok(result) => Ok(Json(json!({
+            "success": true,
+            "import": result
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to import stocktake file {} into {}: {}", params.file_name, params.session_id, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// A stocktake session with its lines, unmatched barcodes and unknown locations
+async fn get_stocktake_session(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.stocktake_service(&tenant_context, &request_context);
+
+    match service.get_session(id).await {
+        Ok(session) => Ok(Json(json!({
+            "success": true,
+            "session": session
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to get stocktake session {}: {}", id, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Submit a reviewed session as cycle counts for approval
+async fn submit_stocktake_session(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.stocktake_service(&tenant_context, &request_context);
+    let user_id = request_context.user_id.ok_or(StatusCode::FORBIDDEN)?;
+
+    match service.submit_session(id, user_id).await {
+        Ok(session) => Ok(Json(json!({
+            "success": true,
+            "session": session
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to submit stocktake session {}: {}", id, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Shrinkage value by location, largest first
+async fn get_stocktake_summary(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.stocktake_service(&tenant_context, &request_context);
+
+    match service.get_shrinkage_summary(id).await {
+        Ok(locations) => {
+            let total: f64 = locations.iter().map(|l| l.shrinkage_value).sum();
+            Ok(Json(json!({
+                "success": true,
+                "session_id": id,
+                "total_shrinkage_value": total,
+                "locations": locations
+            })))
+        }
+        Err(e) => {
+            tracing::error!("Failed to summarize stocktake session {}: {}", id, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Items whose reserved quantity differs from their reservations right now,
+/// and the latest corrections
+async fn get_reservation_drift(
+    State(state): State<AppState>,
+    Extension(tenant_context): Extension<TenantContext>,
+) -> Result<Json<Value>, StatusCode> {
+    match state.reservation_reconciliation.report(tenant_context.tenant_id.0).await {
+        Ok(report) => Ok(Json(json!({
+            "success": true,
+            "report": report
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to build reservation drift report: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Correct one item's reserved quantity now; recorded as an adjustment by the caller
+async fn repair_reservation_drift(
+    State(state): State<AppState>,
+    Path(location_item_id): Path<Uuid>,
+    TenantUser { tenant_context, user_id, .. }: TenantUser,
+) -> Result<Json<Value>, StatusCode> {
+    match state
+        .reservation_reconciliation
+        .repair_item(tenant_context.tenant_id.0, location_item_id, user_id)
+        .await
+    {
+        Ok(outcome) => Ok(Json(json!({
+            "success": true,
+            "result": outcome
+        }))),
+        Err(e) if e.code == ErrorCode::ResourceNotFound => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            tracing::error!("Failed to repair reserved quantity of item {}: {}", location_item_id, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
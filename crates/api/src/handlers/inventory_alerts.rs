@@ -0,0 +1,122 @@
+//! Inventory alert stream handler
+//!
+//! A WebSocket pushing the inventory alerts of the signed-in user's tenant
+//! as they are created, one JSON text message per alert. The upgrade request
+//! is authenticated like any other request; browsers, which cannot set the
+//! `Authorization` header on it, pass the access token as `access_token`.
+//! See [`crate::inventory_alerts`] for the channels and the connection limit.
+
+use axum::{
+    extract::{
+        ws::{close_code, CloseFrame, Message, WebSocket, WebSocketUpgrade}, Query, State,
+    },
+    http::StatusCode,
+    response::{IntoResponse, Json, Response},
+    routing::{get, Router},
+};
+use futures::StreamExt;
+use serde::Deserialize;
+use serde_json::json;
+use std::time::Duration;
+
+use crate::api_middleware::tenant_context::TenantUser;
+use crate::{
+    inventory_alerts::{SeverityFilter, StreamSlot},
+    state::AppState,
+};
+use erp_master_data::inventory::InventoryAlert;
+use redis::aio::PubSub;
+
+/// Pings keep idle streams open through proxies that close quiet connections
+const PING_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Deserialize)]
+pub struct AlertStreamParams {
+    /// Comma-separated severities, e.g. `high,critical`; all if absent
+    pub severity: Option<String>,
+}
+
+/// Create inventory alert routes; they need a user who may read inventory
+pub fn inventory_alert_routes() -> Router<AppState> {
+    Router::new().route("/stream", get(stream_alerts))
+}
+
+/// Upgrade to a WebSocket forwarding the tenant's alerts of the requested severities
+async fn stream_alerts(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, .. }: TenantUser,
+    Query(params): Query<AlertStreamParams>,
+    upgrade: WebSocketUpgrade,
+) -> Result<Response, StatusCode> {
+    let tenant_id = tenant_context.tenant_id.0;
+    let filter = SeverityFilter::parse(params.severity.as_deref()).ok_or(StatusCode::BAD_REQUEST)?;
+
+    let Some(slot) = state.inventory_alerts.claim(tenant_id) else {
+        return Ok((
+            StatusCode::TOO_MANY_REQUESTS,
+            Json(json!({
+                "success": false,
+                "error": "Too many open alert streams for this tenant"
+            })),
+        )
+            .into_response());
+    };
+    // Subscribed before the upgrade, so a client never holds a stream that cannot receive anything
+    let pubsub = state.inventory_alerts.subscribe(tenant_id).await.map_err(|e| {
+        tracing::error!("Failed to subscribe to inventory alerts of tenant {}: {}", tenant_id, e);
+        StatusCode::SERVICE_UNAVAILABLE
+    })?;
+
+    Ok(upgrade.on_upgrade(move |socket| forward_alerts(socket, pubsub, filter, slot)))
+}
+
+/// Forward alerts until the client closes the stream or the subscription ends
+async fn forward_alerts(mut socket: WebSocket, pubsub: PubSub, filter: SeverityFilter, _slot: StreamSlot) {
+    let mut alerts = pubsub.into_on_message();
+    let mut ping = tokio::time::interval(PING_INTERVAL);
+    ping.tick().await;
+
+    loop {
+        tokio::select! {
+            message = alerts.next() => {
+                let Some(message) = message else {
+                    let _ = socket
+                        .send(Message::Close(Some(CloseFrame {
+                            code: close_code::AGAIN,
+                            reason: "Alert feed interrupted, reconnect".into(),
+                        })))
+                        .await;
+                    break;
+                };
+                let payload: String = match message.get_payload() {
+                    Ok(payload) => payload,
+                    Err(e) => {
+                        tracing::warn!("Unreadable inventory alert message: {}", e);
+                        continue;
+                    }
+                };
+                match serde_json::from_str::<InventoryAlert>(&payload) {
+                    Ok(alert) if filter.matches(&alert) => {
+                        if socket.send(Message::Text(payload)).await.is_err() {
+                            break;
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(e) => tracing::warn!("Unreadable inventory alert: {}", e),
+                }
+            }
+            incoming = socket.recv() => match incoming {
+                // Replies to the client's close frame as the connection closes
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+            _ = ping.tick() => {
+                if socket.send(Message::Ping(Vec::new())).await.is_err() {
+                    break;
+                }
+            }
+        }
+    }
+
+    let _ = socket.close().await;
+}
@@ -0,0 +1,110 @@
+//! Inventory costing handlers
+//!
+//! Inbound movements sent without a unit cost get one from the tenant's cost
+//! source chain; the report shows a period's inbound movements by where their
+//! cost came from and which costs wait for review. A wrong cost is restated
+//! by reversing the movement and booking it again at the new cost. Reading
+//! needs `inventory:read`, changing the settings and restating costs
+//! `inventory:write`.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json,
+    routing::{get, post, Router},
+};
+use chrono::NaiveDate;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::api_middleware::tenant_context::TenantUser;
+use crate::state::AppState;
+use erp_master_data::inventory::costing::{CostRestatementRequest, CostingSettings};
+use erp_master_data::MasterDataError;
+
+#[derive(Debug, Deserialize)]
+pub struct ReportParams {
+    pub from: NaiveDate,
+    /// Inclusive
+    pub to: NaiveDate,
+}
+
+/// Create inventory costing routes; they need an authenticated user
+pub fn inventory_costing_routes() -> Router<AppState> {
+    Router::new()
+        .route("/settings", get(get_settings).put(update_settings))
+        .route("/report", get(cost_source_report))
+        .route("/movements/:movement_id/restate", post(restate_cost))
+}
+
+fn error_status(e: &MasterDataError) -> StatusCode {
+    match e {
+        MasterDataError::NotFoundError(_) => StatusCode::NOT_FOUND,
+        MasterDataError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+        MasterDataError::PermissionDenied { .. } => StatusCode::FORBIDDEN,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// The reason a request was refused, or a bare 500 for anything unexpected
+fn failure(e: MasterDataError, action: &str) -> Result<(StatusCode, Json<Value>), StatusCode> {
+    match error_status(&e) {
+        StatusCode::INTERNAL_SERVER_ERROR => {
+            tracing::error!("Failed to {}: {}", action, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+        status => Ok((status, Json(json!({ "success": false, "error": e.to_string() })))),
+    }
+}
+
+/// The tenant's cost source chain and whether costing is strict
+async fn get_settings(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+) -> Result<(StatusCode, Json<Value>), StatusCode> {
+    let service = state.inventory_costing_service(&tenant_context, &request_context);
+    match service.settings().await {
+        Ok(settings) => Ok((StatusCode::OK, Json(json!({ "success": true, "settings": settings })))),
+        Err(e) => failure(e, "load inventory costing settings"),
+    }
+}
+
+async fn update_settings(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+    Json(settings): Json<CostingSettings>,
+) -> Result<(StatusCode, Json<Value>), StatusCode> {
+    let service = state.inventory_costing_service(&tenant_context, &request_context);
+    match service.update_settings(settings).await {
+        Ok(settings) => Ok((StatusCode::OK, Json(json!({ "success": true, "settings": settings })))),
+        Err(e) => failure(e, "save inventory costing settings"),
+    }
+}
+
+/// Inbound movements of the period by cost source, with the costs to review
+async fn cost_source_report(
+    State(state): State<AppState>,
+    Query(params): Query<ReportParams>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+) -> Result<(StatusCode, Json<Value>), StatusCode> {
+    let service = state.inventory_costing_service(&tenant_context, &request_context);
+    match service.cost_source_report(params.from, params.to).await {
+        Ok(report) => Ok((StatusCode::OK, Json(json!({ "success": true, "report": report })))),
+        Err(e) => failure(e, &format!("report cost sources from {} to {}", params.from, params.to)),
+    }
+}
+
+/// Reverse the movement at its cost and book it again at the new one
+async fn restate_cost(
+    State(state): State<AppState>,
+    Path(movement_id): Path<Uuid>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+    Json(request): Json<CostRestatementRequest>,
+) -> Result<(StatusCode, Json<Value>), StatusCode> {
+    let service = state.inventory_costing_service(&tenant_context, &request_context);
+    match service.restate_cost(movement_id, request).await {
+        Ok(restatement) => Ok((StatusCode::CREATED, Json(json!({ "success": true, "restatement": restatement })))),
+        Err(e) => failure(e, &format!("restate the cost of movement {}", movement_id)),
+    }
+}
@@ -0,0 +1,179 @@
+//! Inventory snapshot handlers
+//!
+//! A snapshot records the stock of a location today, of all its products or
+//! of those in a scope (product ids, category, ABC class, lowest unit cost).
+//! Snapshots keep their scope and are only compared with snapshots of the
+//! same location and scope; any other pair is refused with the reason. A
+//! month-end snapshot can be scheduled per location with a scope of its own.
+//! Reading needs `inventory:read`, taking and scheduling snapshots
+//! `inventory:write`.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json,
+    routing::{get, put, Router},
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::api_middleware::tenant_context::TenantUser;
+use crate::state::AppState;
+use erp_master_data::inventory::SnapshotScope;
+use erp_master_data::MasterDataError;
+
+#[derive(Debug, Deserialize)]
+pub struct ListParams {
+    pub location_id: Option<Uuid>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateSnapshotRequest {
+    pub location_id: Uuid,
+    /// The whole location if not given
+    pub scope: Option<SnapshotScope>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CompareParams {
+    pub base: Uuid,
+    pub other: Uuid,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ScheduleRequest {
+    /// The whole location if not given
+    pub scope: Option<SnapshotScope>,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// Create inventory snapshot routes; they need an authenticated user
+pub fn inventory_snapshot_routes() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list_snapshots).post(create_snapshot))
+        .route("/compare", get(compare_snapshots))
+        .route("/schedules", get(list_schedules))
+        .route("/schedules/:location_id", put(set_schedule).delete(delete_schedule))
+        .route("/:snapshot_id", get(get_snapshot))
+}
+
+fn error_status(e: &MasterDataError) -> StatusCode {
+    match e {
+        MasterDataError::NotFoundError(_) | MasterDataError::LocationNotFound { .. } => StatusCode::NOT_FOUND,
+        MasterDataError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+        MasterDataError::PermissionDenied { .. } => StatusCode::FORBIDDEN,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// The reason a request was refused, or a bare 500 for anything unexpected
+fn failure(e: MasterDataError, action: &str) -> Result<(StatusCode, Json<Value>), StatusCode> {
+    match error_status(&e) {
+        StatusCode::INTERNAL_SERVER_ERROR => {
+            tracing::error!("Failed to {}: {}", action, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+        status => Ok((status, Json(json!({ "success": false, "error": e.to_string() })))),
+    }
+}
+
+/// Snapshots of the tenant, optionally of one location, newest first
+async fn list_snapshots(
+    State(state): State<AppState>,
+    Query(params): Query<ListParams>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+) -> Result<(StatusCode, Json<Value>), StatusCode> {
+    let service = state.inventory_snapshot_service(&tenant_context, &request_context);
+    match service.list_snapshots(params.location_id, params.limit.unwrap_or(50)).await {
+        Ok(snapshots) => Ok((StatusCode::OK, Json(json!({ "success": true, "snapshots": snapshots })))),
+        Err(e) => failure(e, "list inventory snapshots"),
+    }
+}
+
+/// Snapshot a location now, of the products in the scope or of all of them
+async fn create_snapshot(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+    Json(request): Json<CreateSnapshotRequest>,
+) -> Result<(StatusCode, Json<Value>), StatusCode> {
+    let service = state.inventory_snapshot_service(&tenant_context, &request_context);
+    match service.create_snapshot(request.location_id, request.scope).await {
+        Ok(snapshot) => Ok((StatusCode::CREATED, Json(json!({ "success": true, "snapshot": snapshot })))),
+        Err(e) => failure(e, &format!("snapshot location {}", request.location_id)),
+    }
+}
+
+/// A snapshot with its lines
+async fn get_snapshot(
+    State(state): State<AppState>,
+    Path(snapshot_id): Path<Uuid>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+) -> Result<(StatusCode, Json<Value>), StatusCode> {
+    let service = state.inventory_snapshot_service(&tenant_context, &request_context);
+    match service.get_snapshot(snapshot_id).await {
+        Ok((snapshot, lines)) => Ok((
+            StatusCode::OK,
+            Json(json!({ "success": true, "snapshot": snapshot, "lines": lines })),
+        )),
+        Err(e) => failure(e, &format!("load inventory snapshot {}", snapshot_id)),
+    }
+}
+
+/// What changed from `base` to `other`; both must be of the same location and scope
+async fn compare_snapshots(
+    State(state): State<AppState>,
+    Query(params): Query<CompareParams>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+) -> Result<(StatusCode, Json<Value>), StatusCode> {
+    let service = state.inventory_snapshot_service(&tenant_context, &request_context);
+    match service.compare_snapshots(params.base, params.other).await {
+        Ok(comparison) => Ok((StatusCode::OK, Json(json!({ "success": true, "comparison": comparison })))),
+        Err(e) => failure(e, &format!("compare inventory snapshots {} and {}", params.base, params.other)),
+    }
+}
+
+/// Month-end snapshots the tenant has scheduled
+async fn list_schedules(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+) -> Result<(StatusCode, Json<Value>), StatusCode> {
+    let service = state.inventory_snapshot_service(&tenant_context, &request_context);
+    match service.list_schedules().await {
+        Ok(schedules) => Ok((StatusCode::OK, Json(json!({ "success": true, "schedules": schedules })))),
+        Err(e) => failure(e, "list inventory snapshot schedules"),
+    }
+}
+
+/// Take a month-end snapshot of the location with the scope
+async fn set_schedule(
+    State(state): State<AppState>,
+    Path(location_id): Path<Uuid>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+    Json(request): Json<ScheduleRequest>,
+) -> Result<(StatusCode, Json<Value>), StatusCode> {
+    let service = state.inventory_snapshot_service(&tenant_context, &request_context);
+    match service.set_schedule(location_id, request.scope, request.enabled).await {
+        Ok(schedule) => Ok((StatusCode::OK, Json(json!({ "success": true, "schedule": schedule })))),
+        Err(e) => failure(e, &format!("schedule snapshots of location {}", location_id)),
+    }
+}
+
+async fn delete_schedule(
+    State(state): State<AppState>,
+    Path(location_id): Path<Uuid>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+) -> Result<(StatusCode, Json<Value>), StatusCode> {
+    let service = state.inventory_snapshot_service(&tenant_context, &request_context);
+    match service.delete_schedule(location_id).await {
+        Ok(true) => Ok((StatusCode::OK, Json(json!({ "success": true })))),
+        Ok(false) => Err(StatusCode::NOT_FOUND),
+        Err(e) => failure(e, &format!("remove the snapshot schedule of location {}", location_id)),
+    }
+}
@@ -0,0 +1,109 @@
+//! Export letterhead handlers
+//!
+//! The legal entity details printed at the top of the signed-in user's
+//! tenant's exports, and the history of their changes. See
+//! [`crate::letterhead`] and [`erp_core::export_header`].
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::Json,
+    routing::{get, put, Router},
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::api_middleware::tenant_context::TenantUser;
+use crate::state::AppState;
+use erp_core::export_header::TenantLetterhead;
+use erp_core::{Error, ErrorCode};
+
+#[derive(Debug, Deserialize)]
+pub struct LetterheadChangesParams {
+    pub limit: Option<i64>,
+}
+
+/// Letterhead reads; they need an authenticated user
+pub fn letterhead_routes() -> Router<AppState> {
+    Router::new().route("/settings/letterhead", get(get_letterhead))
+}
+
+/// Letterhead changes and their history; they need `settings:write`
+pub fn letterhead_admin_routes() -> Router<AppState> {
+    Router::new()
+        .route("/settings/letterhead", put(update_letterhead))
+        .route("/settings/letterhead/changes", get(list_changes))
+}
+
+fn error_status(e: &Error) -> StatusCode {
+    match e.code {
+        ErrorCode::ValidationFailed => StatusCode::BAD_REQUEST,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// The tenant's letterhead; `null` until one is saved
+async fn get_letterhead(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, .. }: TenantUser,
+) -> Result<Json<Value>, StatusCode> {
+
+    match state.letterheads.letterhead(tenant_context.tenant_id.0).await {
+        Ok(letterhead) => Ok(Json(json!({
+            "success": true,
+            "configured": letterhead.is_some(),
+            "letterhead": letterhead
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to load letterhead: {}", e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Replace the letterhead; the previous values are kept in the change history
+async fn update_letterhead(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+    Json(letterhead): Json<TenantLetterhead>,
+) -> Result<(StatusCode, Json<Value>), StatusCode> {
+    let letterhead = match letterhead.normalize_and_validate() {
+        Ok(letterhead) => letterhead,
+        Err(e) => return Ok((StatusCode::BAD_REQUEST, Json(json!({ "success": false, "error": e.message })))),
+    };
+
+    match state
+        .letterheads
+        .save(tenant_context.tenant_id.0, &letterhead, request_context.user_id)
+        .await
+    {
+        Ok(change) => Ok((StatusCode::OK, Json(json!({ "success": true, "letterhead": letterhead, "change": change })))),
+        Err(e) => {
+            tracing::error!("Failed to save letterhead: {}", e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Changes of the letterhead, most recent first
+async fn list_changes(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, .. }: TenantUser,
+    Query(params): Query<LetterheadChangesParams>,
+) -> Result<Json<Value>, StatusCode> {
+
+    match state
+        .letterheads
+        .changes(tenant_context.tenant_id.0, params.limit.unwrap_or(50))
+        .await
+    {
+        Ok(changes) => Ok(Json(json!({
+            "success": true,
+            "changes": changes
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to list letterhead changes: {}", e);
+            Err(error_status(&e))
+        }
+    }
+}
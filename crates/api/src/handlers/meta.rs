@@ -0,0 +1,121 @@
+//! Service metadata handlers
+//!
+//! Unauthenticated; only publishes fields that are safe to expose. The
+//! configuration hash tells whether instances behind a load balancer run with
+//! the same configuration without revealing any of it.
+
+use axum::{
+    response::Json,
+    routing::{get, Router},
+};
+use erp_core::{effective_config, ErrorCode, WarningCode};
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::build_info::{build_info, BuildInfo};
+
+/// Create meta routes
+pub fn meta_routes<S: Clone + Send + Sync + 'static>() -> Router<S> {
+    Router::new()
+        .route("/version", get(version))
+        .route("/error-catalog", get(error_catalog))
+}
+
+/// Build of the running server and the hash of its effective configuration
+#[derive(Debug, Serialize)]
+struct VersionInfo {
+    #[serde(flatten)]
+    build: &'static BuildInfo,
+    /// `None` until the server recorded its configuration at startup
+    config_hash: Option<&'static str>,
+}
+
+/// Build metadata of the running server
+async fn version() -> Json<VersionInfo> {
+    Json(VersionInfo {
+        build: build_info(),
+        config_hash: effective_config::current().map(|snapshot| snapshot.config_hash.as_str()),
+    })
+}
+
+/// Every error code with the status it is returned with, and every warning
+/// code a successful response may carry in its `warnings` array
+async fn error_catalog() -> Json<Value> {
+    let errors: Vec<Value> = ErrorCode::ALL
+        .iter()
+        .map(|code| {
+            json!({
+                "code": code,
+                "number": *code as u16,
+                "http_status": code.http_status(),
+                "category": code.category(),
+                "retryable": code.is_retryable(),
+            })
+        })
+        .collect();
+    let warnings: Vec<Value> = WarningCode::ALL
+        .iter()
+        .map(|code| json!({ "code": code, "description": code.description() }))
+        .collect();
+
+    Json(json!({ "errors": errors, "warnings": warnings }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::{Request, StatusCode}};
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_version_endpoint_shape() {
+        let response = meta_routes::<()>()
+            .oneshot(Request::builder().uri("/version").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+
+        let mut keys: Vec<&str> = body.as_object().unwrap().keys().map(String::as_str).collect();
+        keys.sort_unstable();
+        assert_eq!(keys, ["built_at", "commit", "commit_short", "config_hash", "dirty", "rustc_version", "version"]);
+        assert_eq!(body["version"], env!("CARGO_PKG_VERSION"));
+        assert!(body["dirty"].is_boolean());
+        assert!(body["commit"].as_str().unwrap().starts_with(body["commit_short"].as_str().unwrap()));
+    }
+
+    #[tokio::test]
+    async fn test_error_catalog_lists_errors_and_warnings() {
+        let response = meta_routes::<()>()
+            .oneshot(Request::builder().uri("/error-catalog").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+
+        let errors = body["errors"].as_array().unwrap();
+        assert_eq!(errors.len(), ErrorCode::ALL.len());
+        let not_found = errors.iter().find(|e| e["code"] == "NOT_FOUND").unwrap();
+        assert_eq!(not_found["http_status"], 404);
+        assert_eq!(not_found["number"], 6005);
+
+        let warnings = body["warnings"].as_array().unwrap();
+        let codes: Vec<&str> = warnings.iter().map(|w| w["code"].as_str().unwrap()).collect();
+        assert_eq!(
+            codes,
+            [
+                "TAX_ID_UNVERIFIED",
+                "AI_VALIDATION_SKIPPED",
+                "MASKED_FIELDS_OMITTED",
+                "LOCATION_NEAR_CAPACITY",
+                "SPACE_FACTOR_DEFAULTED",
+                "CAPACITY_OVERRIDDEN",
+            ]
+        );
+        assert!(warnings.iter().all(|w| w["description"].is_string()));
+    }
+}
@@ -6,4 +6,41 @@
 pub mod auth;
 pub mod users;
 pub mod roles;
-pub mod customers;
\ No newline at end of file
+pub mod customers;
+pub mod customer_csv;
+pub mod communications;
+pub mod territories;
+pub mod inventory;
+pub mod inventory_alerts;
+pub mod returns;
+pub mod picking;
+pub mod products;
+pub mod suppliers;
+pub mod admin;
+pub mod sync;
+pub mod meta;
+pub mod activity;
+pub mod audit;
+pub mod async_results;
+pub mod notifications;
+pub mod transfers;
+pub mod capacity;
+pub mod consignment;
+pub mod atp;
+pub mod inventory_snapshots;
+pub mod inventory_costing;
+pub mod admin_lite;
+pub mod calendar;
+pub mod credit_standing;
+pub mod currency;
+pub mod letterhead;
+pub mod sessions;
+pub mod portal_users;
+pub mod reports;
+pub mod api_usage;
+pub mod plan_limits;
+pub mod email_templates;
+pub mod existence;
+pub mod webhooks;
+pub mod search;
+pub mod pagination;
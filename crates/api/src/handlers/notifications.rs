@@ -0,0 +1,91 @@
+//! Notification preference handlers
+//!
+//! The signed-in user's delivery choice per notification category: an email
+//! right away or the daily digest. See [`crate::notification_digest`].
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+    routing::{get, put, Router},
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::api_middleware::tenant_context::TenantUser;
+use crate::{notification_digest::DeliveryMode, state::AppState};
+
+#[derive(Debug, Deserialize)]
+pub struct DeliveryPreferenceRequest {
+    pub delivery: DeliveryMode,
+}
+
+/// Create notification preference routes; they need an authenticated user
+pub fn notification_routes() -> Router<AppState> {
+    Router::new()
+        .route("/notifications/preferences", get(list_preferences))
+        .route("/notifications/preferences/:category", put(set_delivery_preference))
+}
+
+/// Categories the signed-in user has set a preference for
+async fn list_preferences(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, user_id, .. }: TenantUser,
+) -> Result<Json<Value>, StatusCode> {
+
+    match state.notifications.preferences(tenant_context.tenant_id.0, user_id).await {
+        Ok(preferences) => {
+            let digest_categories = &state.config.notification_digest.digest_categories;
+            let preferences: Vec<Value> = preferences
+                .into_iter()
+                .map(|(category, preference)| {
+                    json!({
+                        "category": category,
+                        "email_enabled": preference.email_enabled,
+                        "delivery": preference.delivery,
+                    })
+                })
+                .collect();
+            Ok(Json(json!({
+                "success": true,
+                "preferences": preferences,
+                "digest_by_default": digest_categories
+            })))
+        }
+        Err(e) => {
+            tracing::error!("Failed to load notification preferences for user {}: {}", user_id, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Choose immediate emails or the daily digest for one category
+async fn set_delivery_preference(
+    State(state): State<AppState>,
+    Path(category): Path<String>,
+    TenantUser { tenant_context, user_id, .. }: TenantUser,
+    Json(request): Json<DeliveryPreferenceRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    let valid = !category.is_empty()
+        && category.len() <= 50
+        && category.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+    if !valid {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    match state
+        .notifications
+        .set_delivery(tenant_context.tenant_id.0, user_id, &category, request.delivery)
+        .await
+    {
+        Ok(()) => Ok(Json(json!({
+            "success": true,
+            "category": category,
+            "delivery": request.delivery
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to update notification preference for user {}: {}", user_id, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
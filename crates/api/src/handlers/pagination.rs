@@ -0,0 +1,114 @@
+//! Paginated list responses
+//!
+//! List endpoints answer with [`PaginatedResponse`]: the page's items under
+//! `data` and where the page sits in the whole listing under `meta`.
+
+use axum::response::{IntoResponse, Json, Response};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+/// One page of a listing, serialised as `{ "data": [...], "meta": { ... } }`
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PaginatedResponse<T> {
+    /// Items of this page
+    pub data: Vec<T>,
+    pub meta: PaginationMeta,
+}
+
+/// Position of a page in its listing
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct PaginationMeta {
+    /// Items across all pages
+    pub total: u64,
+    /// 1-based page number
+    pub page: u32,
+    pub per_page: u32,
+    pub total_pages: u32,
+    pub has_next: bool,
+    pub has_prev: bool,
+    /// Cursor to request the next page with, on endpoints paged by cursor
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
+impl<T> PaginatedResponse<T> {
+    /// Page `page` (1-based) of `per_page` items out of `total`
+    pub fn new(data: Vec<T>, total: u64, page: u32, per_page: u32) -> Self {
+        let page = page.max(1);
+        let per_page = per_page.max(1);
+        let total_pages = u32::try_from(total.div_ceil(u64::from(per_page))).unwrap_or(u32::MAX);
+
+        Self {
+            data,
+            meta: PaginationMeta {
+                total,
+                page,
+                per_page,
+                total_pages,
+                has_next: page < total_pages,
+                has_prev: page > 1,
+                next_cursor: None,
+            },
+        }
+    }
+
+    /// A cursor for the next page; its presence alone means there is one
+    pub fn with_next_cursor(mut self, next_cursor: Option<String>) -> Self {
+        self.meta.has_next |= next_cursor.is_some();
+        self.meta.next_cursor = next_cursor;
+        self
+    }
+}
+
+impl<T: Serialize> IntoResponse for PaginatedResponse<T> {
+    fn into_response(self) -> Response {
+        Json(self).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_meta_of_middle_page() {
+        let response = PaginatedResponse::new(vec![1, 2], 5, 2, 2);
+        assert_eq!(response.meta.total_pages, 3);
+        assert!(response.meta.has_next);
+        assert!(response.meta.has_prev);
+    }
+
+    #[test]
+    fn test_empty_listing_has_no_pages() {
+        let response = PaginatedResponse::<u32>::new(Vec::new(), 0, 0, 0);
+        assert_eq!(response.meta.page, 1);
+        assert_eq!(response.meta.per_page, 1);
+        assert_eq!(response.meta.total_pages, 0);
+        assert!(!response.meta.has_next);
+        assert!(!response.meta.has_prev);
+    }
+
+    #[test]
+    fn test_serialises_data_and_meta() {
+        let response = PaginatedResponse::new(vec!["a"], 3, 3, 1).with_next_cursor(None);
+        assert_eq!(
+            serde_json::to_value(&response).unwrap(),
+            json!({
+                "data": ["a"],
+                "meta": {
+                    "total": 3,
+                    "page": 3,
+                    "per_page": 1,
+                    "total_pages": 3,
+                    "has_next": false,
+                    "has_prev": true
+                }
+            })
+        );
+
+        let cursored = PaginatedResponse::new(vec!["a"], 3, 3, 1).with_next_cursor(Some("c".into()));
+        assert!(cursored.meta.has_next);
+        assert_eq!(serde_json::to_value(&cursored).unwrap()["meta"]["next_cursor"], "c");
+    }
+}
@@ -0,0 +1,187 @@
+//! Pick wave handlers
+//!
+//! Waves group the open reservations of one location, chosen by hand or by
+//! cutoff time, into a pick list sorted along the walking path. Pickers fetch
+//! the waves assigned to them, confirm each line from the scanner and the
+//! wave is closed to book the picks. Every call acts as the authenticated
+//! user.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json,
+    routing::{get, post, Router},
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::api_middleware::tenant_context::TenantUser;
+use crate::state::AppState;
+use erp_master_data::inventory::{ConfirmPickRequest, CreatePickWaveRequest, WaveStatus};
+use erp_master_data::MasterDataError;
+
+#[derive(Debug, Deserialize)]
+pub struct WaveListParams {
+    pub status: Option<WaveStatus>,
+}
+
+/// Create pick wave routes; they need an authenticated user
+pub fn picking_routes() -> Router<AppState> {
+    Router::new()
+        .route("/waves", post(create_wave).get(list_waves))
+        .route("/my-pick-list", get(my_pick_list))
+        .route("/waves/:id", get(get_wave))
+        .route("/waves/:id/summary", get(get_summary))
+        .route("/waves/:id/lines/:line_id/confirm", post(confirm_line))
+        .route("/waves/:id/close", post(close_wave))
+}
+
+fn error_status(e: &MasterDataError) -> StatusCode {
+    match e {
+        MasterDataError::NotFoundError(_) => StatusCode::NOT_FOUND,
+        MasterDataError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+        MasterDataError::InvalidWaveTransition { .. } => StatusCode::CONFLICT,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// Group the selected reservations into a wave, lines in path order
+async fn create_wave(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+    Json(request): Json<CreatePickWaveRequest>,
+) -> Result<(StatusCode, Json<Value>), StatusCode> {
+    let service = state.picking_service(&tenant_context, &request_context);
+
+    match service.create_wave(request).await {
+        Ok(wave) => Ok((StatusCode::CREATED, Json(json!({
+            "success": true,
+            "wave": wave
+        })))),
+        Err(e) => {
+            tracing::error!("Failed to create pick wave: {}", e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Waves, most recently created first, optionally filtered by status
+async fn list_waves(
+    State(state): State<AppState>,
+    Query(params): Query<WaveListParams>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.picking_service(&tenant_context, &request_context);
+
+    match service.list_waves(params.status).await {
+        Ok(waves) => Ok(Json(json!({
+            "success": true,
+            "waves": waves
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to list pick waves: {}", e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Open waves assigned to the caller, lines in path order
+async fn my_pick_list(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.picking_service(&tenant_context, &request_context);
+
+    match service.my_pick_lists().await {
+        Ok(waves) => Ok(Json(json!({
+            "success": true,
+            "waves": waves
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to get pick list: {}", e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+async fn get_wave(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.picking_service(&tenant_context, &request_context);
+
+    match service.get_wave(id).await {
+        Ok(wave) => Ok(Json(json!({
+            "success": true,
+            "wave": wave
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to get pick wave {}: {}", id, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Completion percentage and short-pick exceptions of a wave
+async fn get_summary(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.picking_service(&tenant_context, &request_context);
+
+    match service.get_summary(id).await {
+        Ok(summary) => Ok(Json(json!({
+            "success": true,
+            "summary": summary
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to summarize pick wave {}: {}", id, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Confirm one line from the scanner; a short pick is re-allocated or backordered
+async fn confirm_line(
+    State(state): State<AppState>,
+    Path((id, line_id)): Path<(Uuid, Uuid)>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+    Json(request): Json<ConfirmPickRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.picking_service(&tenant_context, &request_context);
+
+    match service.confirm_line(id, line_id, request).await {
+        Ok(line) => Ok(Json(json!({
+            "success": true,
+            "line": line
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to confirm line {} of pick wave {}: {}", line_id, id, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Book the confirmed picks as outbound movements in one transaction
+async fn close_wave(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.picking_service(&tenant_context, &request_context);
+
+    match service.close_wave(id).await {
+        Ok(wave) => Ok(Json(json!({
+            "success": true,
+            "summary": wave.summary(),
+            "wave": wave
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to close pick wave {}: {}", id, e);
+            Err(error_status(&e))
+        }
+    }
+}
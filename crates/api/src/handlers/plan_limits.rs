@@ -0,0 +1,39 @@
+//! Plan limit handlers
+//!
+//! Usage of the signed-in user's tenant against the limits of its plan: the
+//! current value and share of each limit, whether reaching it blocks creating
+//! more, and the change over the last 7 and 30 days from the nightly
+//! measurements; see [`crate::plan_limits`]. It needs `settings:write`.
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::Json,
+    routing::{get, Router},
+};
+use chrono::Utc;
+use serde_json::{json, Value};
+
+use crate::api_middleware::tenant_context::TenantUser;
+use crate::state::AppState;
+
+/// Plan limit usage; it needs `settings:write`
+pub fn plan_limit_routes() -> Router<AppState> {
+    Router::new().route("/usage/limits", get(limit_usage))
+}
+
+/// Each limit of the tenant's plan with its current usage and trend
+async fn limit_usage(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, .. }: TenantUser,
+) -> Result<(StatusCode, Json<Value>), StatusCode> {
+
+    let today = Utc::now().date_naive();
+    match state.plan_limits.report(tenant_context.tenant_id.0, today).await {
+        Ok(limits) => Ok((StatusCode::OK, Json(json!({ "success": true, "limits": limits, "as_of": today })))),
+        Err(e) => {
+            tracing::error!("Failed to load plan limit usage: {}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
@@ -0,0 +1,167 @@
+//! Customer portal user handlers
+//!
+//! Invitation, acceptance and revocation of the limited accounts a customer's
+//! contacts use to see their own data. See [`erp_core::portal`] for the rules
+//! their tokens follow and [`erp_auth::AuthService::invite_portal_user`] for
+//! the account lifecycle.
+
+use axum::{
+    extract::{Extension, Path, State},
+    http::StatusCode,
+    response::Json,
+    routing::{get, post, Router},
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::api_middleware::tenant_context::TenantUser;
+use crate::state::AppState;
+use erp_auth::dto::{AcceptPortalInvitationRequest, InvitePortalUserRequest};
+use erp_core::{Error, ErrorCode, TenantContext};
+
+#[derive(Debug, Deserialize)]
+pub struct InvitePortalUserBody {
+    /// The customer contact to invite; its email receives the account
+    pub contact_id: Uuid,
+    /// Also let the account see the customers below this one
+    #[serde(default)]
+    pub include_children: bool,
+}
+
+/// Invitation and revocation; they need `customers:write`
+pub fn portal_user_routes() -> Router<AppState> {
+    Router::new()
+        .route("/customers/:id/portal-users", get(list_portal_users).post(invite_portal_user))
+        .route("/customers/:id/portal-users/:user_id/revoke", post(revoke_portal_user))
+}
+
+/// Acceptance; the invitee has no account to authenticate with yet
+pub fn portal_invitation_routes() -> Router<AppState> {
+    Router::new().route("/customers/:id/portal-users/accept", post(accept_invitation))
+}
+
+fn error_status(e: &Error) -> StatusCode {
+    match e.code {
+        ErrorCode::ValidationFailed => StatusCode::BAD_REQUEST,
+        ErrorCode::TokenInvalid => StatusCode::BAD_REQUEST,
+        ErrorCode::ResourceNotFound => StatusCode::NOT_FOUND,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// Portal accounts of a customer, revoked ones included
+async fn list_portal_users(
+    State(state): State<AppState>,
+    Path(customer_id): Path<Uuid>,
+    TenantUser { tenant_context, .. }: TenantUser,
+) -> Result<Json<Value>, StatusCode> {
+
+    match state.auth_service.list_portal_users(&tenant_context, customer_id).await {
+        Ok(accounts) => Ok(Json(json!({ "success": true, "portal_users": accounts }))),
+        Err(e) => {
+            tracing::error!("Failed to list portal users of customer {}: {}", customer_id, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Invite one of the customer's contacts
+///
+/// The response carries the invitation token once; it is not stored in
+/// plain and has to reach the contact through the inviting user.
+async fn invite_portal_user(
+    State(state): State<AppState>,
+    Path(customer_id): Path<Uuid>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+    Json(body): Json<InvitePortalUserBody>,
+) -> Result<(StatusCode, Json<Value>), StatusCode> {
+
+    let repository = state.customer_repository(tenant_context.clone());
+    match repository.get_customer_by_id(customer_id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            tracing::error!("Failed to load customer {}: {}", customer_id, e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    }
+    let contact = match repository.get_customer_contacts(customer_id).await {
+        Ok(contacts) => contacts
+            .into_iter()
+            .find(|contact| contact.id == body.contact_id && contact.is_active)
+            .ok_or(StatusCode::NOT_FOUND)?,
+        Err(e) => {
+            tracing::error!("Failed to load contacts of customer {}: {}", customer_id, e);
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+    let Some(email) = contact.email.clone() else {
+        return Ok((StatusCode::BAD_REQUEST, Json(json!({
+            "success": false,
+            "error": "The contact has no email address"
+        }))));
+    };
+
+    let request = InvitePortalUserRequest {
+        customer_id,
+        contact_id: Some(contact.id),
+        email,
+        first_name: Some(contact.first_name),
+        last_name: Some(contact.last_name),
+        include_children: body.include_children,
+    };
+    match state.auth_service.invite_portal_user(&tenant_context, request, request_context.user_id).await {
+        Ok((account, invitation_token)) => Ok((StatusCode::CREATED, Json(json!({
+            "success": true,
+            "portal_user": account,
+            "invitation_token": invitation_token
+        })))),
+        Err(e) if e.code == ErrorCode::ValidationFailed => {
+            Ok((StatusCode::BAD_REQUEST, Json(json!({ "success": false, "error": e.message }))))
+        }
+        Err(e) => {
+            tracing::error!("Failed to invite portal user for customer {}: {}", customer_id, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Set the password of an invited account, which also verifies its email
+async fn accept_invitation(
+    State(state): State<AppState>,
+    Path(customer_id): Path<Uuid>,
+    Extension(tenant_context): Extension<TenantContext>,
+    Json(request): Json<AcceptPortalInvitationRequest>,
+) -> Result<(StatusCode, Json<Value>), StatusCode> {
+    match state.auth_service.accept_portal_invitation(&tenant_context, customer_id, request).await {
+        Ok(account) => Ok((StatusCode::OK, Json(json!({ "success": true, "portal_user": account })))),
+        Err(e) if matches!(e.code, ErrorCode::ValidationFailed | ErrorCode::TokenInvalid) => {
+            Ok((StatusCode::BAD_REQUEST, Json(json!({ "success": false, "error": e.message }))))
+        }
+        Err(e) => {
+            tracing::error!("Failed to accept portal invitation for customer {}: {}", customer_id, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Revoke an account; its sessions and tokens stop working immediately
+async fn revoke_portal_user(
+    State(state): State<AppState>,
+    Path((customer_id, user_id)): Path<(Uuid, Uuid)>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+) -> Result<Json<Value>, StatusCode> {
+
+    match state
+        .auth_service
+        .revoke_portal_user(&tenant_context, customer_id, user_id, request_context.user_id)
+        .await
+    {
+        Ok(account) => Ok(Json(json!({ "success": true, "portal_user": account }))),
+        Err(e) => {
+            tracing::error!("Failed to revoke portal user {} of customer {}: {}", user_id, customer_id, e);
+            Err(error_status(&e))
+        }
+    }
+}
@@ -0,0 +1,914 @@
+//! Product handlers
+//!
+//! Products: single products and pages of the catalog, prices in the shape of the requested API version.
+//! Bulk price updates: dry-run diffs, guarded updates and rollback.
+//! Barcodes: validation for label printing, assignment and a report of bad existing data.
+//! Price lists: per customer group and channel, with CSV line loading and price resolution.
+//! Suggestions: review of the AI engine's optimization suggestions, and the tenant's auto-apply opt-in.
+//! Integrity: products without lifecycle and rows left behind by deleted products, and their repair.
+//! Quality inspections: the inspection calendar, results, and dispositions of failed batches.
+//! Feed export: the catalog as a JSON, CSV or XLSX download.
+
+use axum::{
+    body::Body,
+    extract::{Extension, Path, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Json, Response},
+    routing::{get, post, put, Router},
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::api_middleware::api_version::ApiVersion;
+use crate::api_middleware::tenant_context::TenantUser;
+use crate::handlers::pagination::PaginatedResponse;
+use crate::responses::product_response;
+use crate::state::AppState;
+use erp_core::{Error, ErrorCode};
+use erp_master_data::product::{
+    AutoApplyRule, BatchDisposition, BulkPriceUpdateStatus, InspectionResult, LifecycleAutomationMode, LifecycleStage,
+    PriceListRequest, RescheduleInspectionRequest, ScheduleInspectionRequest, SuggestionFilter, ValidateBarcodeRequest,
+};
+use erp_master_data::product::feed::export_product_feed;
+use erp_master_data::product::repository::{
+    AdvancedProductSearch as RepoAdvancedSearch, BulkPriceUpdateRequest, PaginationOptions, PriceContext,
+};
+use erp_master_data::product::{AdvancedProductSearch, PostgresProductRepository, ProductFeedFormat, ProductRepository};
+
+/// Products per page unless the request asks for fewer
+const MAX_PRODUCTS_PER_PAGE: u32 = 100;
+
+/// Create product routes; they need an authenticated user
+pub fn product_routes() -> Router<AppState> {
+    Router::new()
+        .route("/prices/bulk", post(bulk_update_prices))
+        .route("/prices/bulk/:id", get(get_bulk_price_update))
+        .route("/prices/bulk/:id/rollback", post(rollback_bulk_price_update))
+        .route("/validate-barcode", post(validate_barcode))
+        .route("/barcodes/report", get(barcode_report))
+        .route("/:id/barcode", put(assign_barcode))
+        .route("/:id/price", get(effective_price))
+        .route("/price-lists", get(list_price_lists).post(create_price_list))
+        .route("/price-lists/:id", get(get_price_list).put(update_price_list).delete(delete_price_list))
+        .route("/price-lists/:id/lines", post(import_price_list_lines))
+        .route("/suggestions", get(list_suggestions))
+        .route("/suggestions/accept", post(bulk_accept_suggestions))
+        .route("/suggestions/auto-apply", get(get_auto_apply_rules).put(set_auto_apply_rules))
+        .route("/suggestions/:id/accept", post(accept_suggestion))
+        .route("/suggestions/:id/reject", post(reject_suggestion))
+        .route("/lifecycle/automation", get(get_lifecycle_automation).put(set_lifecycle_automation))
+        .route("/:id/lifecycle", get(get_lifecycle).put(set_lifecycle_stage))
+        .route("/integrity", get(get_product_integrity))
+        .route("/integrity/repair", post(repair_product_integrity))
+        .route("/inspections", get(upcoming_inspections).post(schedule_inspection))
+        .route("/inspections/overdue", get(overdue_inspections))
+        .route("/inspections/:id", get(get_inspection))
+        .route("/inspections/:id/result", post(record_inspection_result))
+        .route("/inspections/:id/reschedule", put(reschedule_inspection))
+        .route("/batches/:id/disposition", post(disposition_batch))
+}
+
+/// Product reads; they need a user who may read products
+pub fn product_read_routes() -> Router<AppState> {
+    Router::new()
+        .route("/products", get(list_products))
+        .route("/products/:id", get(get_product))
+}
+
+/// Product feed export; it belongs in the exports concurrency group and needs a user who may read products
+pub fn product_export_routes() -> Router<AppState> {
+    Router::new().route("/products/export", post(export_products))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ProductExportRequest {
+    /// `json`, `csv` or `xlsx`
+    #[serde(default = "default_feed_format")]
+    pub format: String,
+    /// Only the products matching it; the whole catalog if not given
+    pub filter: Option<AdvancedProductSearch>,
+}
+
+fn default_feed_format() -> String {
+    "json".to_string()
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ProductListParams {
+    /// 1-based page
+    #[serde(default = "default_page")]
+    pub page: u32,
+    #[serde(default = "default_per_page")]
+    pub limit: u32,
+}
+
+fn default_page() -> u32 {
+    1
+}
+
+fn default_per_page() -> u32 {
+    20
+}
+
+fn error_status(e: &Error) -> StatusCode {
+    match e.code {
+        ErrorCode::NotFound | ErrorCode::ResourceNotFound => StatusCode::NOT_FOUND,
+        ErrorCode::ValidationFailed => StatusCode::BAD_REQUEST,
+        ErrorCode::PermissionDenied => StatusCode::FORBIDDEN,
+        ErrorCode::ConflictError => StatusCode::CONFLICT,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// A page of the tenant's products, newest first
+async fn list_products(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, .. }: TenantUser,
+    Extension(version): Extension<ApiVersion>,
+    Query(params): Query<ProductListParams>,
+) -> Result<Response, StatusCode> {
+    let page = params.page.max(1);
+    let per_page = params.limit.clamp(1, MAX_PRODUCTS_PER_PAGE);
+    let pagination = PaginationOptions { page: i64::from(page), limit: i64::from(per_page) };
+
+    let repository = PostgresProductRepository::new(state.db.clone());
+    let listing = repository
+        .search_products_advanced(tenant_context.tenant_id.0, &RepoAdvancedSearch::default(), &pagination)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to list products: {}", e);
+            error_status(&e)
+        })?;
+
+    let products = listing
+        .items
+        .iter()
+        .map(|product| product_response(version, product))
+        .collect::<Result<Vec<_>, _>>()?;
+    let total = u64::try_from(listing.total).unwrap_or_default();
+    Ok(PaginatedResponse::new(products, total, page, per_page).into_response())
+}
+
+/// One product of the tenant
+async fn get_product(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    TenantUser { tenant_context, .. }: TenantUser,
+    Extension(version): Extension<ApiVersion>,
+) -> Result<Json<Value>, StatusCode> {
+    let repository = PostgresProductRepository::new(state.db.clone());
+    match repository.get_product_by_id(tenant_context.tenant_id.0, id).await {
+        Ok(Some(product)) => Ok(Json(json!({ "success": true, "product": product_response(version, &product)? }))),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            tracing::error!("Failed to load product {}: {}", id, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Preview or apply a bulk price update; a blocked batch is returned with 409 and its violations
+async fn bulk_update_prices(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+    Json(request): Json<BulkPriceUpdateRequest>,
+) -> Result<(StatusCode, Json<Value>), StatusCode> {
+    let service = state.bulk_price_update_service(&tenant_context, &request_context);
+
+    match service.bulk_update_prices(request).await {
+        Ok(outcome) => {
+            let status = match outcome.status {
+                BulkPriceUpdateStatus::Blocked => StatusCode::CONFLICT,
+                BulkPriceUpdateStatus::Preview | BulkPriceUpdateStatus::Applied => StatusCode::OK,
+            };
+            Ok((status, Json(json!({
+                "success": outcome.status != BulkPriceUpdateStatus::Blocked,
+                "status": outcome.status,
+                "bulk_update_id": outcome.bulk_update_id,
+                "diff": outcome.diff
+            }))))
+        }
+        Err(e) => {
+            tracing::error!("Bulk price update failed: {}", e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+async fn get_bulk_price_update(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.bulk_price_update_service(&tenant_context, &request_context);
+
+    match service.get_bulk_price_update(id).await {
+        Ok(record) => Ok(Json(json!({
+            "success": true,
+            "bulk_update": record
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to get bulk price update {}: {}", id, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Restore the prices from before a bulk update
+async fn rollback_bulk_price_update(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.bulk_price_update_service(&tenant_context, &request_context);
+
+    match service.rollback_bulk_price_update(id).await {
+        Ok(record) => Ok(Json(json!({
+            "success": true,
+            "bulk_update": record
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to roll back bulk price update {}: {}", id, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Check a barcode before printing labels: format, check digit, GS1 batch and
+/// expiry, and whether another product or variant already uses it. Always 200;
+/// the verdict is in `valid` and `available`.
+async fn validate_barcode(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+    Json(request): Json<ValidateBarcodeRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.barcode_service(&tenant_context, &request_context);
+
+    match service.validate_barcode(request).await {
+        Ok(validation) => Ok(Json(json!({
+            "success": true,
+            "validation": validation
+        }))),
+        Err(e) => {
+            tracing::error!("Barcode validation failed: {}", e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct AssignBarcodeRequest {
+    /// `None` or blank clears the barcode
+    barcode: Option<String>,
+    /// Set the barcode of this variant of the product instead
+    variant_id: Option<Uuid>,
+}
+
+/// Validate and store the barcode of a product or variant; 409 if it is taken
+async fn assign_barcode(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+    Json(request): Json<AssignBarcodeRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.barcode_service(&tenant_context, &request_context);
+
+    match service.assign_barcode(id, request.variant_id, request.barcode).await {
+        Ok(barcode) => Ok(Json(json!({
+            "success": true,
+            "product_id": id,
+            "variant_id": request.variant_id,
+            "barcode": barcode
+        }))),
+        Err(e) => {
+            tracing::warn!("Failed to assign barcode to product {}: {}", id, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Existing barcodes that are invalid, not normalized or shared between products
+async fn barcode_report(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.barcode_service(&tenant_context, &request_context);
+
+    match service.barcode_report().await {
+        Ok(report) => Ok(Json(json!({
+            "success": true,
+            "report": report
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to build barcode report: {}", e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+async fn list_price_lists(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.price_list_service(&tenant_context, &request_context);
+
+    match service.list_price_lists().await {
+        Ok(price_lists) => Ok(Json(json!({
+            "success": true,
+            "price_lists": price_lists
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to list price lists: {}", e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+async fn get_price_list(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.price_list_service(&tenant_context, &request_context);
+
+    match service.get_price_list(id).await {
+        Ok(price_list) => Ok(Json(json!({
+            "success": true,
+            "price_list": price_list
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to get price list {}: {}", id, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Create a price list; 409 if it ties with an overlapping list of the same priority
+async fn create_price_list(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+    Json(request): Json<PriceListRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.price_list_service(&tenant_context, &request_context);
+
+    match service.create_price_list(request).await {
+        Ok(price_list) => Ok(Json(json!({
+            "success": true,
+            "price_list": price_list
+        }))),
+        Err(e) => {
+            tracing::warn!("Failed to create price list: {}", e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+async fn update_price_list(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+    Json(request): Json<PriceListRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.price_list_service(&tenant_context, &request_context);
+
+    match service.update_price_list(id, request).await {
+        Ok(price_list) => Ok(Json(json!({
+            "success": true,
+            "price_list": price_list
+        }))),
+        Err(e) => {
+            tracing::warn!("Failed to update price list {}: {}", id, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+async fn delete_price_list(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.price_list_service(&tenant_context, &request_context);
+
+    match service.delete_price_list(id).await {
+        Ok(()) => Ok(Json(json!({
+            "success": true,
+            "price_list_id": id
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to delete price list {}: {}", id, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Load lines into a price list from a CSV body
+/// (`sku,variant_sku,price,discount_percent,currency`). Rows that cannot be
+/// loaded are listed in `errors`; the rest are stored.
+async fn import_price_list_lines(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+    body: String,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.price_list_service(&tenant_context, &request_context);
+
+    match service.import_lines_csv(id, &body).await {
+        Ok(result) => Ok(Json(json!({
+            "success": result.errors.is_empty(),
+            "import": result
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to load lines into price list {}: {}", id, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct EffectivePriceParams {
+    variant_id: Option<Uuid>,
+    customer_group: Option<String>,
+    channel: Option<String>,
+    currency: Option<String>,
+    quantity: Option<i32>,
+    /// Defaults to now
+    at: Option<DateTime<Utc>>,
+}
+
+/// Price of a product or variant for a customer group and channel
+async fn effective_price(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+    Query(params): Query<EffectivePriceParams>,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.price_list_service(&tenant_context, &request_context);
+    let context = PriceContext {
+        customer_tier: None,
+        quantity: params.quantity,
+        location: None,
+        date_time: params.at.unwrap_or_else(Utc::now),
+        customer_group: params.customer_group,
+        channel: params.channel,
+        currency: params.currency,
+    };
+
+    match service.effective_price(id, params.variant_id, &context).await {
+        Ok(price) => Ok(Json(json!({
+            "success": true,
+            "product_id": id,
+            "variant_id": params.variant_id,
+            "price": price
+        }))),
+        Err(e) => {
+            tracing::warn!("Failed to price product {}: {}", id, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Pending suggestions; those made for a product that changed since are expired, not listed
+async fn list_suggestions(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+    Query(filter): Query<SuggestionFilter>,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.product_suggestion_service(&tenant_context, &request_context);
+
+    match service.list_pending(filter).await {
+        Ok(suggestions) => Ok(Json(json!({
+            "success": true,
+            "suggestions": suggestions
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to list product suggestions: {}", e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Apply a suggestion through the product update; 409 if the product changed since
+async fn accept_suggestion(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.product_suggestion_service(&tenant_context, &request_context);
+
+    match service.accept(id).await {
+        Ok(suggestion) => Ok(Json(json!({
+            "success": true,
+            "suggestion": suggestion
+        }))),
+        Err(e) => {
+            tracing::warn!("Failed to accept product suggestion {}: {}", id, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct RejectSuggestionRequest {
+    reason: String,
+}
+
+async fn reject_suggestion(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+    Json(request): Json<RejectSuggestionRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.product_suggestion_service(&tenant_context, &request_context);
+
+    match service.reject(id, request.reason).await {
+        Ok(suggestion) => Ok(Json(json!({
+            "success": true,
+            "suggestion": suggestion
+        }))),
+        Err(e) => {
+            tracing::warn!("Failed to reject product suggestion {}: {}", id, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Accept every pending suggestion matching the filter; refused ones stay pending
+async fn bulk_accept_suggestions(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+    Json(filter): Json<SuggestionFilter>,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.product_suggestion_service(&tenant_context, &request_context);
+
+    match service.bulk_accept(filter).await {
+        Ok(outcome) => Ok(Json(json!({
+            "success": true,
+            "accepted": outcome.accepted,
+            "expired": outcome.expired,
+            "failed": outcome.failed
+        }))),
+        Err(e) => {
+            tracing::error!("Bulk suggestion accept failed: {}", e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+async fn get_auto_apply_rules(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.product_suggestion_service(&tenant_context, &request_context);
+
+    match service.auto_apply_rules().await {
+        Ok(rules) => Ok(Json(json!({
+            "success": true,
+            "rules": rules
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to load suggestion auto-apply rules: {}", e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct AutoApplyRulesRequest {
+    rules: Vec<AutoApplyRule>,
+}
+
+/// Replace the suggestion types applied without review; an empty list turns it off
+async fn set_auto_apply_rules(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+    Json(request): Json<AutoApplyRulesRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.product_suggestion_service(&tenant_context, &request_context);
+
+    match service.set_auto_apply_rules(request.rules).await {
+        Ok(rules) => Ok(Json(json!({
+            "success": true,
+            "rules": rules
+        }))),
+        Err(e) => {
+            tracing::warn!("Failed to set suggestion auto-apply rules: {}", e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// The tenant's lifecycle automation mode
+async fn get_lifecycle_automation(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.lifecycle_automation_service(&tenant_context, &request_context);
+
+    match service.automation_mode().await {
+        Ok(mode) => Ok(Json(json!({
+            "success": true,
+            "mode": mode
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to load lifecycle automation mode: {}", e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct LifecycleAutomationRequest {
+    mode: LifecycleAutomationMode,
+}
+
+/// Choose whether lifecycle transitions are proposed, applied or not made
+async fn set_lifecycle_automation(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+    Json(request): Json<LifecycleAutomationRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.lifecycle_automation_service(&tenant_context, &request_context);
+
+    match service.set_automation_mode(request.mode).await {
+        Ok(mode) => Ok(Json(json!({
+            "success": true,
+            "mode": mode
+        }))),
+        Err(e) => {
+            tracing::warn!("Failed to set lifecycle automation mode: {}", e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// The product's lifecycle with its evaluation against the lifecycle rules
+async fn get_lifecycle(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.lifecycle_automation_service(&tenant_context, &request_context);
+
+    match service.evaluate_product(id, Utc::now()).await {
+        Ok(evaluation) => Ok(Json(json!({
+            "success": true,
+            "evaluation": evaluation
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to evaluate lifecycle of product {}: {}", id, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct LifecycleStageRequest {
+    stage: LifecycleStage,
+}
+
+/// Set the stage by hand; the automation leaves it alone for the freeze window
+async fn set_lifecycle_stage(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+    Json(request): Json<LifecycleStageRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.lifecycle_automation_service(&tenant_context, &request_context);
+
+    match service.set_stage(id, request.stage).await {
+        Ok(lifecycle) => Ok(Json(json!({
+            "success": true,
+            "lifecycle": lifecycle
+        }))),
+        Err(e) => {
+            tracing::warn!("Failed to set lifecycle stage of product {}: {}", id, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Products without lifecycle record and rows whose product is gone
+async fn get_product_integrity(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, .. }: TenantUser,
+) -> Result<Json<Value>, StatusCode> {
+
+    match state.product_integrity_repository().integrity_report(tenant_context.tenant_id.0).await {
+        Ok(report) => Ok(Json(json!({
+            "success": true,
+            "consistent": report.is_consistent(),
+            "report": report
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to check product integrity: {}", e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Delete the orphaned rows and give products without lifecycle record one
+async fn repair_product_integrity(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+) -> Result<Json<Value>, StatusCode> {
+    let user_id = request_context.user_id.ok_or(StatusCode::FORBIDDEN)?;
+
+    match state
+        .product_integrity_repository()
+        .repair_integrity(tenant_context.tenant_id.0, user_id, Utc::now())
+        .await
+    {
+        Ok(repair) => Ok(Json(json!({
+            "success": true,
+            "repair": repair
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to repair product integrity: {}", e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct UpcomingInspectionParams {
+    /// Defaults to 14
+    days: Option<i64>,
+}
+
+/// Open inspections scheduled within the next days
+async fn upcoming_inspections(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+    Query(params): Query<UpcomingInspectionParams>,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.quality_inspection_service(&tenant_context, &request_context);
+
+    match service.upcoming(Utc::now(), params.days.unwrap_or(14)).await {
+        Ok(inspections) => Ok(Json(json!({
+            "success": true,
+            "inspections": inspections
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to load upcoming inspections: {}", e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Open inspections past their scheduled date
+async fn overdue_inspections(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.quality_inspection_service(&tenant_context, &request_context);
+
+    match service.overdue(Utc::now()).await {
+        Ok(inspections) => Ok(Json(json!({
+            "success": true,
+            "inspections": inspections
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to load overdue inspections: {}", e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Schedule an inspection by hand
+async fn schedule_inspection(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+    Json(request): Json<ScheduleInspectionRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.quality_inspection_service(&tenant_context, &request_context);
+
+    match service.schedule(request).await {
+        Ok(inspection) => Ok(Json(json!({
+            "success": true,
+            "inspection": inspection
+        }))),
+        Err(e) => {
+            tracing::warn!("Failed to schedule inspection: {}", e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+async fn get_inspection(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.quality_inspection_service(&tenant_context, &request_context);
+
+    match service.get_inspection(id).await {
+        Ok(inspection) => Ok(Json(json!({
+            "success": true,
+            "inspection": inspection
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to load inspection {}: {}", id, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Record the outcome; it sets the quality status of the inspected batch
+async fn record_inspection_result(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+    Json(result): Json<InspectionResult>,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.quality_inspection_service(&tenant_context, &request_context);
+
+    match service.record_result(id, result).await {
+        Ok(inspection) => Ok(Json(json!({
+            "success": true,
+            "inspection": inspection
+        }))),
+        Err(e) => {
+            tracing::warn!("Failed to record result of inspection {}: {}", id, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+async fn reschedule_inspection(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+    Json(request): Json<RescheduleInspectionRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.quality_inspection_service(&tenant_context, &request_context);
+
+    match service.reschedule(id, request).await {
+        Ok(inspection) => Ok(Json(json!({
+            "success": true,
+            "inspection": inspection
+        }))),
+        Err(e) => {
+            tracing::warn!("Failed to reschedule inspection {}: {}", id, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct BatchDispositionRequest {
+    disposition: BatchDisposition,
+}
+
+/// Decide what happens to a failed or recalled batch; it releases the hold on its units
+async fn disposition_batch(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+    Json(request): Json<BatchDispositionRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.quality_inspection_service(&tenant_context, &request_context);
+
+    match service.disposition_batch(id, request.disposition).await {
+        Ok(batch) => Ok(Json(json!({
+            "success": true,
+            "batch": batch
+        }))),
+        Err(e) => {
+            tracing::warn!("Failed to disposition batch {}: {}", id, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// The catalog, or the part matching the filter, as a file download
+async fn export_products(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, .. }: TenantUser,
+    Json(request): Json<ProductExportRequest>,
+) -> Result<Response, StatusCode> {
+    let tenant_id = tenant_context.tenant_id.0;
+    let format = match ProductFeedFormat::parse(&request.format) {
+        Ok(format) => format,
+        Err(e) => return Ok((StatusCode::BAD_REQUEST, Json(json!({ "success": false, "error": e.message }))).into_response()),
+    };
+    let search = request.filter.map(RepoAdvancedSearch::from);
+
+    let repository = PostgresProductRepository::new(state.db.clone());
+    let feed = export_product_feed(&repository, tenant_id, format, search.as_ref()).await.map_err(|e| {
+        tracing::error!("Failed to export the product feed of tenant {}: {}", tenant_id, e);
+        error_status(&e)
+    })?;
+
+    let filename = format!("products-{}-{}.{}", tenant_id, Utc::now().format("%Y%m%d%H%M%S"), format.extension());
+    Ok((
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename)),
+        ],
+        Body::from(feed.body),
+    )
+        .into_response())
+}
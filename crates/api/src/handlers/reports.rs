@@ -0,0 +1,138 @@
+//! Inventory report handlers
+//!
+//! Stock valuation, inventory KPIs, product margins and the valuation
+//! dashboard, stated in the tenant's base currency. Amounts in other
+//! currencies are converted with the rate effective on `as_of` and listed
+//! with their original amounts and the applied rates. A report that would
+//! need a missing rate fails with 422 naming the currencies without one
+//! rather than adding up amounts of different currencies.
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::Json,
+    routing::{get, Router},
+};
+use chrono::{NaiveDate, Utc};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::api_middleware::tenant_context::TenantUser;
+use crate::state::AppState;
+use erp_master_data::inventory::StockValueFilter;
+use erp_master_data::MasterDataError;
+
+#[derive(Debug, Deserialize)]
+pub struct ReportParams {
+    pub location_id: Option<Uuid>,
+    pub category_id: Option<Uuid>,
+    /// Valuation date whose exchange rates apply; defaults to today (UTC)
+    pub as_of: Option<NaiveDate>,
+}
+
+impl ReportParams {
+    fn filter(&self) -> StockValueFilter {
+        StockValueFilter {
+            location_id: self.location_id,
+            category_id: self.category_id,
+        }
+    }
+
+    fn valuation_date(&self) -> NaiveDate {
+        self.as_of.unwrap_or_else(|| Utc::now().date_naive())
+    }
+}
+
+/// Create inventory report routes; they need an authenticated user
+pub fn report_routes() -> Router<AppState> {
+    Router::new()
+        .route("/inventory-valuation", get(inventory_valuation))
+        .route("/inventory-kpis", get(inventory_kpis))
+        .route("/profitability", get(profitability))
+        .route("/dashboard", get(dashboard))
+}
+
+/// Missing rates are answered with the currencies lacking one
+fn error_response(e: MasterDataError, report: &str) -> Result<(StatusCode, Json<Value>), StatusCode> {
+    match e {
+        MasterDataError::ExchangeRateMissing {
+            ref base_currency,
+            ref currencies,
+            ref date,
+        } => Ok((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(json!({
+                "success": false,
+                "error": e.to_string(),
+                "base_currency": base_currency,
+                "missing_currencies": currencies.split(", ").collect::<Vec<_>>(),
+                "valuation_date": date
+            })),
+        )),
+        MasterDataError::ValidationError { .. } => {
+            Ok((StatusCode::BAD_REQUEST, Json(json!({ "success": false, "error": e.to_string() }))))
+        }
+        MasterDataError::NotFoundError(_) => Err(StatusCode::NOT_FOUND),
+        e => {
+            tracing::error!("Failed to build {} report: {}", report, e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Cost value of the stock per product and location
+async fn inventory_valuation(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+    Query(params): Query<ReportParams>,
+) -> Result<(StatusCode, Json<Value>), StatusCode> {
+    let service = state.inventory_valuation_service(&tenant_context, &request_context);
+
+    match service.valuation(&params.filter(), params.valuation_date()).await {
+        Ok(valuation) => Ok((StatusCode::OK, Json(json!({ "success": true, "valuation": valuation })))),
+        Err(e) => error_response(e, "inventory valuation"),
+    }
+}
+
+/// Stock, retail value and potential margin in totals
+async fn inventory_kpis(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+    Query(params): Query<ReportParams>,
+) -> Result<(StatusCode, Json<Value>), StatusCode> {
+    let service = state.inventory_valuation_service(&tenant_context, &request_context);
+
+    match service.kpis(&params.filter(), params.valuation_date()).await {
+        Ok(kpis) => Ok((StatusCode::OK, Json(json!({ "success": true, "kpis": kpis })))),
+        Err(e) => error_response(e, "inventory KPI"),
+    }
+}
+
+/// Unit and stock margin of each product
+async fn profitability(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+    Query(params): Query<ReportParams>,
+) -> Result<(StatusCode, Json<Value>), StatusCode> {
+    let service = state.inventory_valuation_service(&tenant_context, &request_context);
+
+    match service.product_margins(&params.filter(), params.valuation_date()).await {
+        Ok(report) => Ok((StatusCode::OK, Json(json!({ "success": true, "profitability": report })))),
+        Err(e) => error_response(e, "profitability"),
+    }
+}
+
+/// Stock value by location and the most valuable products
+async fn dashboard(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+    Query(params): Query<ReportParams>,
+) -> Result<(StatusCode, Json<Value>), StatusCode> {
+    let service = state.inventory_valuation_service(&tenant_context, &request_context);
+
+    match service.dashboard(&params.filter(), params.valuation_date()).await {
+        Ok(dashboard) => Ok((StatusCode::OK, Json(json!({ "success": true, "dashboard": dashboard })))),
+        Err(e) => error_response(e, "valuation dashboard"),
+    }
+}
@@ -0,0 +1,205 @@
+//! Customer return handlers
+//!
+//! Return orders (RMAs) are created, received into quarantine, inspected
+//! line by line and reported on as return rates per product. Every call acts
+//! as the authenticated user.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json,
+    routing::{get, post, Router},
+};
+use chrono::{DateTime, Duration, Utc};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::api_middleware::tenant_context::TenantUser;
+use crate::state::AppState;
+use erp_master_data::inventory::{
+    CreateReturnOrderRequest, InspectReturnRequest, ReceiveReturnRequest, ReturnStatus, };
+use erp_master_data::MasterDataError;
+
+#[derive(Debug, Deserialize)]
+pub struct ReturnListParams {
+    pub status: Option<ReturnStatus>,
+    pub customer_id: Option<Uuid>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReturnRateParams {
+    /// Defaults to 90 days before `period_end`
+    pub period_start: Option<DateTime<Utc>>,
+    /// Defaults to now
+    pub period_end: Option<DateTime<Utc>>,
+}
+
+/// Create return routes; they need an authenticated user
+pub fn return_routes() -> Router<AppState> {
+    Router::new()
+        .route("/", post(create_return).get(list_returns))
+        .route("/reports/return-rates", get(get_return_rates))
+        .route("/:id", get(get_return))
+        .route("/:id/receive", post(receive_return))
+        .route("/:id/inspect", post(inspect_return))
+        .route("/:id/cancel", post(cancel_return))
+}
+
+fn error_status(e: &MasterDataError) -> StatusCode {
+    match e {
+        MasterDataError::NotFoundError(_) => StatusCode::NOT_FOUND,
+        MasterDataError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+        MasterDataError::InvalidReturnTransition { .. } => StatusCode::CONFLICT,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// Register a return order in `requested`
+async fn create_return(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+    Json(request): Json<CreateReturnOrderRequest>,
+) -> Result<(StatusCode, Json<Value>), StatusCode> {
+    let service = state.returns_service(&tenant_context, &request_context);
+    let user_id = request_context.user_id.ok_or(StatusCode::FORBIDDEN)?;
+
+    match service.create_return(request, user_id).await {
+        Ok(order) => Ok((StatusCode::CREATED, Json(json!({
+            "success": true,
+            "return": order
+        })))),
+        Err(e) => {
+            tracing::error!("Failed to create return order: {}", e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Return orders, newest first, optionally filtered by status or customer
+async fn list_returns(
+    State(state): State<AppState>,
+    Query(params): Query<ReturnListParams>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.returns_service(&tenant_context, &request_context);
+
+    match service.list_returns(params.status, params.customer_id).await {
+        Ok(orders) => Ok(Json(json!({
+            "success": true,
+            "returns": orders
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to list return orders: {}", e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// A return order with its lines and dispositions
+async fn get_return(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.returns_service(&tenant_context, &request_context);
+
+    match service.get_return(id).await {
+        Ok(order) => Ok(Json(json!({
+            "success": true,
+            "return": order
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to get return order {}: {}", id, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Book the returned goods into a quarantine location
+async fn receive_return(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+    Json(request): Json<ReceiveReturnRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.returns_service(&tenant_context, &request_context);
+    let user_id = request_context.user_id.ok_or(StatusCode::FORBIDDEN)?;
+
+    match service.receive_return(id, request, user_id).await {
+        Ok(order) => Ok(Json(json!({
+            "success": true,
+            "return": order
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to receive return order {}: {}", id, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Record dispositions for received lines and move the units out of quarantine
+async fn inspect_return(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+    Json(request): Json<InspectReturnRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.returns_service(&tenant_context, &request_context);
+    let user_id = request_context.user_id.ok_or(StatusCode::FORBIDDEN)?;
+
+    match service.inspect_return(id, request, user_id).await {
+        Ok(order) => Ok(Json(json!({
+            "success": true,
+            "return": order
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to inspect return order {}: {}", id, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Cancel a return that has not been received yet
+async fn cancel_return(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.returns_service(&tenant_context, &request_context);
+
+    match service.cancel_return(id).await {
+        Ok(order) => Ok(Json(json!({
+            "success": true,
+            "return": order
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to cancel return order {}: {}", id, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Returned units per shipped unit by product, highest rate first
+async fn get_return_rates(
+    State(state): State<AppState>,
+    Query(params): Query<ReturnRateParams>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.returns_service(&tenant_context, &request_context);
+    let period_end = params.period_end.unwrap_or_else(Utc::now);
+    let period_start = params.period_start.unwrap_or(period_end - Duration::days(90));
+
+    match service.get_return_rates(period_start, period_end).await {
+        Ok(products) => Ok(Json(json!({
+            "success": true,
+            "period_start": period_start,
+            "period_end": period_end,
+            "products": products
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to compute return rates: {}", e);
+            Err(error_status(&e))
+        }
+    }
+}
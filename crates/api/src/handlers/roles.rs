@@ -1,20 +1,63 @@
 //! Role management handlers
 //!
-//! HTTP handlers for role and permission management
+//! HTTP handlers for role and permission management. The listings take a
+//! search term, a sort and a page; permissions come grouped by resource for
+//! the role editor. Search terms are described in [`erp_auth::role_catalog`].
 
 use axum::{
-    extract::{State, Path, Extension},
+    extract::{State, Path, Query, Extension},
     http::StatusCode,
-    response::Json,
+    response::{IntoResponse, Json, Response},
     routing::{get, post, put, delete, Router},
 };
 use serde::Deserialize;
 use serde_json::{json, Value};
 use uuid::Uuid;
 
+use crate::handlers::pagination::PaginatedResponse;
 use crate::state::AppState;
 use erp_core::TenantContext;
-use erp_auth::dto::{CreateRoleRequest as AuthCreateRoleRequest, UpdateRoleRequest as AuthUpdateRoleRequest};
+use erp_auth::dto::{CreateRoleRequest as AuthCreateRoleRequest, RoleResponse, UpdateRoleRequest as AuthUpdateRoleRequest};
+use erp_auth::role_catalog::{self, Page, PermissionQuery, PermissionSort, RoleQuery, RoleSort, SearchTerm};
+use erp_core::ErrorCode;
+use chrono::{Duration, Utc};
+
+#[derive(Debug, Deserialize)]
+pub struct RoleListParams {
+    /// Free text over name and description
+    pub search: Option<String>,
+    #[serde(default)]
+    pub sort: RoleSort,
+    /// `asc` (default) or `desc`
+    pub order: Option<String>,
+    #[serde(default = "default_page")]
+    pub page: u32,
+    #[serde(default = "default_limit")]
+    pub limit: u32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PermissionListParams {
+    /// Free text over resource, action and description, or `resource:action`
+    pub search: Option<String>,
+    /// Only this resource
+    pub resource: Option<String>,
+    #[serde(default)]
+    pub sort: PermissionSort,
+    /// `asc` (default) or `desc`
+    pub order: Option<String>,
+    #[serde(default = "default_page")]
+    pub page: u32,
+    #[serde(default = "default_limit")]
+    pub limit: u32,
+}
+
+fn default_page() -> u32 { 1 }
+fn default_limit() -> u32 { 50 }
+
+fn descending(order: &Option<String>) -> bool {
+    order.as_deref().is_some_and(|order| order.eq_ignore_ascii_case("desc"))
+}
 
 #[derive(Debug, Deserialize)]
 pub struct CreateRoleRequest {
@@ -45,19 +88,64 @@ pub fn role_routes() -> Router<AppState> {
         .route("/:id", delete(delete_role))
         .route("/:id/permissions", get(get_role_permissions))
         .route("/:id/permissions", post(assign_permissions))
+        .route("/:id/effective-permissions", get(get_effective_permissions))
 }
 
-/// List all roles
-async fn list_roles(
+/// Create permission routes
+pub fn permission_routes() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list_permissions))
+        .route("/:id/roles", get(get_permission_roles))
+}
+
+fn not_found(e: &erp_core::Error) -> bool {
+    matches!(e.code, ErrorCode::NotFound | ErrorCode::ResourceNotFound)
+}
+
+/// List the roles matching the search, a page at a time
+#[utoipa::path(
+    get,
+    path = "/api/v1/roles",
+    params(
+        ("X-Tenant-Id" = String, Header, description = "Tenant ID"),
+        ("search" = Option<String>, Query, description = "Free text over name and description"),
+        ("sort" = Option<String>, Query, description = "`name` (default) or `created_at`"),
+        ("order" = Option<String>, Query, description = "`asc` (default) or `desc`"),
+        ("page" = Option<u32>, Query, description = "1-based page, defaults to 1"),
+        ("limit" = Option<u32>, Query, description = "Roles per page, defaults to 50, at most 200")
+    ),
+    responses(
+        (status = 200, description = "One page of roles", body = PaginatedResponse<RoleResponse>),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Insufficient permissions"),
+    ),
+    tag = "roles",
+    security(
+        ("bearer_auth" = ["role:manage"])
+    )
+)]
+pub async fn list_roles(
     State(state): State<AppState>,
+    Query(params): Query<RoleListParams>,
     Extension(tenant_context): Extension<TenantContext>,
-) -> Result<Json<Value>, StatusCode> {
-    match state.auth_service.list_roles(&tenant_context).await {
-        Ok(roles) => {
-            Ok(Json(json!({
-                "success": true,
-                "roles": roles
-            })))
+) -> Result<Response, StatusCode> {
+    let query = RoleQuery {
+        search: params.search.as_deref().and_then(SearchTerm::parse),
+        sort: params.sort,
+        descending: descending(&params.order),
+        page: Page::new(params.page, params.limit),
+    };
+
+    match state.auth_service.search_roles(&tenant_context, &query).await {
+        Ok(listing) => {
+            let pagination = listing.pagination;
+            Ok(PaginatedResponse::new(
+                listing.roles,
+                pagination.total.max(0) as u64,
+                pagination.page,
+                pagination.limit,
+            )
+            .into_response())
         }
         Err(e) => {
             tracing::error!("Failed to list roles: {}", e);
@@ -65,7 +153,7 @@ async fn list_roles(
                 "success": false,
                 "error": "Failed to retrieve roles",
                 "message": e.to_string()
-            })))
+            })).into_response())
         }
     }
 }
@@ -249,4 +337,100 @@ async fn assign_permissions(
             })))
         }
     }
-}
\ No newline at end of file
+}
+/// A role's permissions by resource, with the other roles granting each and
+/// when the tenant last used it
+async fn get_effective_permissions(
+    State(state): State<AppState>,
+    Path(role_id): Path<Uuid>,
+    Extension(tenant_context): Extension<TenantContext>,
+) -> Result<Json<Value>, StatusCode> {
+    let (role, mut permissions) = match state.auth_service.effective_permissions(&tenant_context, role_id).await {
+        Ok(expanded) => expanded,
+        Err(e) if not_found(&e) => return Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            tracing::error!("Failed to expand permissions of role {}: {}", role_id, e);
+            return Ok(Json(json!({
+                "success": false,
+                "error": "Failed to retrieve effective permissions",
+                "message": e.to_string()
+            })));
+        }
+    };
+
+    // Without usage tracking the usage fields stay empty
+    let usage = state.permission_usage.config();
+    if usage.enabled {
+        let since = Utc::now().date_naive() - Duration::days(usage.default_window_days);
+        match state.permission_usage.last_used(tenant_context.tenant_id.0).await {
+            Ok(last_used) => role_catalog::with_usage(&mut permissions, &last_used, since),
+            Err(e) => tracing::warn!("Failed to load permission usage of role {}: {}", role_id, e),
+        }
+    }
+
+    Ok(Json(json!({
+        "success": true,
+        "role": role,
+        "permission_count": permissions.len(),
+        "groups": role_catalog::group_by_resource(permissions)
+    })))
+}
+
+/// List the permissions matching the search, grouped by resource
+async fn list_permissions(
+    State(state): State<AppState>,
+    Query(params): Query<PermissionListParams>,
+    Extension(tenant_context): Extension<TenantContext>,
+) -> Result<Json<Value>, StatusCode> {
+    let query = PermissionQuery {
+        search: params.search.as_deref().and_then(SearchTerm::parse),
+        resource: params.resource.filter(|resource| !resource.trim().is_empty()),
+        sort: params.sort,
+        descending: descending(&params.order),
+        page: Page::new(params.page, params.limit),
+    };
+
+    match state.auth_service.search_permissions(&tenant_context, &query).await {
+        Ok(listing) => {
+            Ok(Json(json!({
+                "success": true,
+                "groups": listing.groups,
+                "pagination": listing.pagination
+            })))
+        }
+        Err(e) => {
+            tracing::error!("Failed to list permissions: {}", e);
+            Ok(Json(json!({
+                "success": false,
+                "error": "Failed to retrieve permissions",
+                "message": e.to_string()
+            })))
+        }
+    }
+}
+
+/// The roles granting a permission
+async fn get_permission_roles(
+    State(state): State<AppState>,
+    Path(permission_id): Path<Uuid>,
+    Extension(tenant_context): Extension<TenantContext>,
+) -> Result<Json<Value>, StatusCode> {
+    match state.auth_service.permission_roles(&tenant_context, permission_id).await {
+        Ok((permission, roles)) => {
+            Ok(Json(json!({
+                "success": true,
+                "permission": permission,
+                "roles": roles
+            })))
+        }
+        Err(e) if not_found(&e) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            tracing::error!("Failed to look up roles of permission {}: {}", permission_id, e);
+            Ok(Json(json!({
+                "success": false,
+                "error": "Failed to retrieve roles",
+                "message": e.to_string()
+            })))
+        }
+    }
+}
@@ -0,0 +1,73 @@
+//! Global search handlers
+//!
+//! Type-ahead search of the signed-in user's tenant across customers,
+//! products, suppliers and locations, grouped by type. See
+//! [`crate::global_search`] for permissions, timeouts and scoring.
+
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::Json,
+    routing::{get, Router},
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::api_middleware::tenant_context::TenantUser;
+use crate::{
+    global_search::{SearchAccess, SearchType, MAX_QUERY_CHARS},
+    state::AppState,
+};
+
+#[derive(Debug, Deserialize)]
+pub struct SearchParams {
+    pub q: Option<String>,
+    /// Comma-separated types, e.g. `customers,products`; all if absent
+    pub types: Option<String>,
+    /// Results per type
+    pub limit: Option<usize>,
+}
+
+/// Create global search routes; they need an authenticated user
+pub fn search_routes() -> Router<AppState> {
+    Router::new().route("/", get(global_search))
+}
+
+fn search_types(types: Option<&str>) -> Result<Vec<SearchType>, StatusCode> {
+    types
+        .unwrap_or_default()
+        .split(',')
+        .map(|t| t.trim().to_lowercase())
+        .filter(|t| !t.is_empty())
+        .map(|t| SearchType::parse(&t).ok_or(StatusCode::BAD_REQUEST))
+        .collect()
+}
+
+/// Best matches per type for the query
+async fn global_search(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+    Query(params): Query<SearchParams>,
+) -> Result<Json<Value>, StatusCode> {
+    let query = params.q.as_deref().map(str::trim).unwrap_or_default();
+    if query.is_empty() || query.chars().count() > MAX_QUERY_CHARS {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let types = search_types(params.types.as_deref())?;
+    let limit = state.global_search.limit(params.limit);
+
+    let access = SearchAccess {
+        tenant_id: tenant_context.tenant_id.0,
+        permissions: request_context.permissions.iter().map(|p| p.to_string()).collect(),
+        portal: request_context.portal,
+    };
+    let sources = state.search_sources(&tenant_context, request_context.portal);
+    let results = state.global_search.search(&access, query, &types, limit, sources).await;
+
+    Ok(Json(json!({
+        "success": true,
+        "query": results.query,
+        "groups": results.groups,
+        "truncated": results.truncated
+    })))
+}
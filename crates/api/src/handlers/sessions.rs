@@ -0,0 +1,76 @@
+//! Session limit handlers
+//!
+//! How many sessions one user of the signed-in user's tenant may hold at once,
+//! and whether a login beyond that signs out the oldest session or is refused.
+//! The same settings turn on sliding sessions, which hand out a fresh access
+//! token before the current one expires. See [`erp_auth::session_limit`].
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::Json,
+    routing::{get, put, Router},
+};
+use serde_json::{json, Value};
+
+use crate::api_middleware::tenant_context::TenantUser;
+use crate::state::AppState;
+use erp_core::{Error, ErrorCode, SessionLimit};
+
+/// Session limit reads; they need an authenticated user
+pub fn session_limit_routes() -> Router<AppState> {
+    Router::new().route("/settings/sessions", get(get_session_limit))
+}
+
+/// Session limit changes; they need `settings:write`
+pub fn session_limit_admin_routes() -> Router<AppState> {
+    Router::new().route("/settings/sessions", put(update_session_limit))
+}
+
+fn error_status(e: &Error) -> StatusCode {
+    match e.code {
+        ErrorCode::ValidationFailed => StatusCode::BAD_REQUEST,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// The tenant's session limit, or the default one if the tenant has not set its own
+async fn get_session_limit(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, .. }: TenantUser,
+) -> Result<Json<Value>, StatusCode> {
+
+    match state.auth_service.session_limit(&tenant_context).await {
+        Ok(limit) => Ok(Json(json!({
+            "success": true,
+            "session_limit": limit
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to load session limit: {}", e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Replace the session limit; sessions already open are left alone until the user's next login
+async fn update_session_limit(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+    Json(limit): Json<SessionLimit>,
+) -> Result<(StatusCode, Json<Value>), StatusCode> {
+    let Some(user_id) = request_context.user_id else {
+        return Err(StatusCode::FORBIDDEN);
+    };
+
+    match state.auth_service.set_session_limit(&tenant_context, limit, user_id).await {
+        Ok(limit) => Ok((StatusCode::OK, Json(json!({ "success": true, "session_limit": limit })))),
+        Err(e) if e.code == ErrorCode::ValidationFailed => Ok((
+            StatusCode::BAD_REQUEST,
+            Json(json!({ "success": false, "error": e.details.unwrap_or(e.message) })),
+        )),
+        Err(e) => {
+            tracing::error!("Failed to save session limit: {}", e);
+            Err(error_status(&e))
+        }
+    }
+}
@@ -0,0 +1,183 @@
+//! Supplier catalog handlers
+//!
+//! A supplier's catalog prices the products it sells with quantity price
+//! breaks, ordering constraints and a validity window. Quotes pick the best
+//! valid entry for a quantity, the price in the supplier's currency. Reading
+//! needs `suppliers:read`, changing a catalog `suppliers:write`. Every call
+//! acts as the authenticated user.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json,
+    routing::{get, post, put, Router},
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::api_middleware::tenant_context::TenantUser;
+use crate::state::AppState;
+use erp_core::{Error, ErrorCode};
+use erp_master_data::supplier::{CatalogQuoteRequest, CreateCatalogEntryRequest, UpdateCatalogEntryRequest};
+
+#[derive(Debug, Deserialize)]
+pub struct CatalogQuoteParams {
+    pub quantity: i32,
+    /// Now if not given
+    pub as_of: Option<DateTime<Utc>>,
+    pub max_lead_time_days: Option<i32>,
+    /// Quote this supplier's entry even if another is cheaper
+    pub supplier_id: Option<Uuid>,
+}
+
+/// Catalog reads; they need a user who may read suppliers
+pub fn supplier_catalog_routes() -> Router<AppState> {
+    Router::new()
+        .route("/suppliers/:id/catalog", get(get_supplier_catalog))
+        .route("/products/:id/supplier-catalog", get(get_product_catalog))
+        .route("/products/:id/supplier-catalog/quote", get(quote_product))
+}
+
+/// Catalog changes; they need a user who may change suppliers
+pub fn supplier_catalog_admin_routes() -> Router<AppState> {
+    Router::new()
+        .route("/suppliers/:id/catalog", post(create_catalog_entry))
+        .route("/suppliers/:id/catalog/:entry_id", put(update_catalog_entry).delete(delete_catalog_entry))
+}
+
+fn error_status(e: &Error) -> StatusCode {
+    match e.code {
+        ErrorCode::NotFound | ErrorCode::ResourceNotFound => StatusCode::NOT_FOUND,
+        ErrorCode::ValidationFailed => StatusCode::BAD_REQUEST,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// A supplier's catalog entries
+async fn get_supplier_catalog(
+    State(state): State<AppState>,
+    Path(supplier_id): Path<Uuid>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.supplier_catalog_service(&tenant_context, &request_context);
+
+    match service.get_supplier_catalog(supplier_id).await {
+        Ok(entries) => Ok(Json(json!({
+            "success": true,
+            "entries": entries
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to list the catalog of supplier {}: {}", supplier_id, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Every supplier's catalog entries for a product
+async fn get_product_catalog(
+    State(state): State<AppState>,
+    Path(product_id): Path<Uuid>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.supplier_catalog_service(&tenant_context, &request_context);
+
+    match service.get_product_catalog_entries(product_id).await {
+        Ok(entries) => Ok(Json(json!({
+            "success": true,
+            "entries": entries
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to list the catalog entries of product {}: {}", product_id, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// The best valid catalog entry for buying a quantity of a product; 404 if none fits
+async fn quote_product(
+    State(state): State<AppState>,
+    Path(product_id): Path<Uuid>,
+    Query(params): Query<CatalogQuoteParams>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.supplier_catalog_service(&tenant_context, &request_context);
+    let request = CatalogQuoteRequest {
+        quantity: params.quantity,
+        as_of: params.as_of,
+        max_lead_time_days: params.max_lead_time_days,
+        supplier_override: params.supplier_id,
+    };
+
+    match service.quote_product(product_id, request).await {
+        Ok(Some(quote)) => Ok(Json(json!({
+            "success": true,
+            "quote": quote
+        }))),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            tracing::warn!("Failed to quote product {}: {}", product_id, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+async fn create_catalog_entry(
+    State(state): State<AppState>,
+    Path(supplier_id): Path<Uuid>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+    Json(request): Json<CreateCatalogEntryRequest>,
+) -> Result<(StatusCode, Json<Value>), StatusCode> {
+    let service = state.supplier_catalog_service(&tenant_context, &request_context);
+
+    match service.create_catalog_entry(supplier_id, request).await {
+        Ok(entry) => Ok((
+            StatusCode::CREATED,
+            Json(json!({
+                "success": true,
+                "entry": entry
+            })),
+        )),
+        Err(e) => {
+            tracing::warn!("Failed to add a catalog entry for supplier {}: {}", supplier_id, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+async fn update_catalog_entry(
+    State(state): State<AppState>,
+    Path((supplier_id, entry_id)): Path<(Uuid, Uuid)>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+    Json(request): Json<UpdateCatalogEntryRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.supplier_catalog_service(&tenant_context, &request_context);
+
+    match service.update_catalog_entry(supplier_id, entry_id, request).await {
+        Ok(entry) => Ok(Json(json!({
+            "success": true,
+            "entry": entry
+        }))),
+        Err(e) => {
+            tracing::warn!("Failed to update catalog entry {}: {}", entry_id, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+async fn delete_catalog_entry(
+    State(state): State<AppState>,
+    Path((supplier_id, entry_id)): Path<(Uuid, Uuid)>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+) -> Result<StatusCode, StatusCode> {
+    let service = state.supplier_catalog_service(&tenant_context, &request_context);
+
+    match service.delete_catalog_entry(supplier_id, entry_id).await {
+        Ok(()) => Ok(StatusCode::NO_CONTENT),
+        Err(e) => {
+            tracing::error!("Failed to delete catalog entry {}: {}", entry_id, e);
+            Err(error_status(&e))
+        }
+    }
+}
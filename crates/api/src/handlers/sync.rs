@@ -0,0 +1,84 @@
+//! Differential sync handlers
+//!
+//! Changed-since feeds for offline clients. See [`crate::sync`] for the cursor semantics.
+
+use axum::{
+    extract::{Extension, Query, State},
+    http::StatusCode,
+    response::Json,
+    routing::{get, Router},
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::{state::AppState, sync::SyncEntity};
+use erp_core::TenantContext;
+
+#[derive(Debug, Deserialize)]
+pub struct SyncParams {
+    /// Cursor returned by the previous sync; omit for the first sync
+    #[serde(default)]
+    pub since: i64,
+}
+
+/// Create sync routes
+pub fn sync_routes() -> Router<AppState> {
+    Router::new()
+        .route("/customers", get(sync_customers))
+        .route("/products", get(sync_products))
+        .route("/customer-ownership", get(sync_customer_ownership))
+}
+
+/// Customers changed since the cursor
+async fn sync_customers(
+    State(state): State<AppState>,
+    Extension(tenant_context): Extension<TenantContext>,
+    Query(params): Query<SyncParams>,
+) -> Result<Json<Value>, StatusCode> {
+    changes_since(&state, &tenant_context, SyncEntity::Customer, params.since).await
+}
+
+/// Products changed since the cursor
+async fn sync_products(
+    State(state): State<AppState>,
+    Extension(tenant_context): Extension<TenantContext>,
+    Query(params): Query<SyncParams>,
+) -> Result<Json<Value>, StatusCode> {
+    changes_since(&state, &tenant_context, SyncEntity::Product, params.since).await
+}
+
+/// Changes of customers' sales representative or account manager since the cursor
+async fn sync_customer_ownership(
+    State(state): State<AppState>,
+    Extension(tenant_context): Extension<TenantContext>,
+    Query(params): Query<SyncParams>,
+) -> Result<Json<Value>, StatusCode> {
+    changes_since(&state, &tenant_context, SyncEntity::CustomerOwnership, params.since).await
+}
+
+/// One page of `entity` changes after the cursor, as the sync and credit status feeds return it
+pub(crate) async fn changes_since(
+    state: &AppState,
+    tenant_context: &TenantContext,
+    entity: SyncEntity,
+    since: i64,
+) -> Result<Json<Value>, StatusCode> {
+    if since < 0 {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    match state.sync.changes_since(tenant_context, entity, since).await {
+        Ok(page) => Ok(Json(json!({
+            "success": true,
+            "entity": page.entity,
+            "changes": page.changes,
+            "next_cursor": page.next_cursor,
+            "has_more": page.has_more,
+            "full_resync_required": page.full_resync_required
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to read {} changes: {}", entity.as_str(), e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
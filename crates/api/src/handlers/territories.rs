@@ -0,0 +1,240 @@
+//! Sales territory handlers
+//!
+//! Territory management, previews of which customers rules match and what a
+//! re-assignment would move, the re-assignment itself, and the assignment of
+//! single customers with their overrides. See
+//! [`erp_master_data::customer::territory`] for how assignees are derived.
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::Json,
+    routing::{get, post, Router},
+};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::api_middleware::tenant_context::TenantUser;
+use crate::state::AppState;
+use erp_master_data::customer::{AssignmentOverrideRequest, ReassignmentPlan, TerritoryRequest, TerritoryRules};
+use erp_master_data::MasterDataError;
+
+/// Create territory routes; they need an authenticated tenant user
+pub fn territory_routes() -> Router<AppState> {
+    Router::new()
+        .route("/territories", get(list_territories).post(create_territory))
+        .route("/territories/preview-matches", post(preview_matches))
+        .route("/territories/reassignment/preview", post(preview_reassignment))
+        .route("/territories/reassignment", post(reassign))
+        .route(
+            "/territories/:id",
+            get(get_territory).put(update_territory).delete(delete_territory),
+        )
+        .route("/customers/:id/assignment", get(get_assignment).put(set_overrides))
+}
+
+fn error_status(e: &MasterDataError) -> StatusCode {
+    match e {
+        MasterDataError::CustomerNotFound { .. } | MasterDataError::TerritoryNotFound { .. } => StatusCode::NOT_FOUND,
+        MasterDataError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+        MasterDataError::PermissionDenied { .. } => StatusCode::FORBIDDEN,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+fn plan_json(plan: &ReassignmentPlan) -> Value {
+    json!({
+        "success": true,
+        "customers_evaluated": plan.customers_evaluated,
+        "customers_moving": plan.customers_moving,
+        "moves": plan.moves,
+        "assignments": plan.assignments
+    })
+}
+
+/// Territories by descending priority
+async fn list_territories(
+    State(state): State<AppState>,
+    user: TenantUser,
+) -> Result<Json<Value>, StatusCode> {
+    let TenantUser { tenant_context, request_context, .. } = user.staff_only()?;
+    let service = state.territory_service(&tenant_context, &request_context);
+
+    match service.list_territories().await {
+        Ok(territories) => Ok(Json(json!({
+            "success": true,
+            "count": territories.len(),
+            "territories": territories
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to list territories: {}", e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Create a territory; customers are assigned to it by the next re-assignment
+async fn create_territory(
+    State(state): State<AppState>,
+    user: TenantUser,
+    Json(request): Json<TerritoryRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    let TenantUser { tenant_context, request_context, .. } = user.staff_only()?;
+    let service = state.territory_service(&tenant_context, &request_context);
+
+    match service.create_territory(request).await {
+        Ok(territory) => Ok(Json(json!({ "success": true, "territory": territory }))),
+        Err(e) => {
+            tracing::error!("Failed to create territory: {}", e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+async fn get_territory(
+    State(state): State<AppState>,
+    Path(territory_id): Path<Uuid>,
+    user: TenantUser,
+) -> Result<Json<Value>, StatusCode> {
+    let TenantUser { tenant_context, request_context, .. } = user.staff_only()?;
+    let service = state.territory_service(&tenant_context, &request_context);
+
+    match service.get_territory(territory_id).await {
+        Ok(territory) => Ok(Json(json!({ "success": true, "territory": territory }))),
+        Err(e) => Err(error_status(&e)),
+    }
+}
+
+/// Replace a territory's definition
+async fn update_territory(
+    State(state): State<AppState>,
+    Path(territory_id): Path<Uuid>,
+    user: TenantUser,
+    Json(request): Json<TerritoryRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    let TenantUser { tenant_context, request_context, .. } = user.staff_only()?;
+    let service = state.territory_service(&tenant_context, &request_context);
+
+    match service.update_territory(territory_id, request).await {
+        Ok(territory) => Ok(Json(json!({ "success": true, "territory": territory }))),
+        Err(e) => {
+            tracing::error!("Failed to update territory {}: {}", territory_id, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+async fn delete_territory(
+    State(state): State<AppState>,
+    Path(territory_id): Path<Uuid>,
+    user: TenantUser,
+) -> Result<Json<Value>, StatusCode> {
+    let TenantUser { tenant_context, request_context, .. } = user.staff_only()?;
+    let service = state.territory_service(&tenant_context, &request_context);
+
+    match service.delete_territory(territory_id).await {
+        Ok(()) => Ok(Json(json!({ "success": true }))),
+        Err(e) => {
+            tracing::error!("Failed to delete territory {}: {}", territory_id, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Customers the given rules match
+async fn preview_matches(
+    State(state): State<AppState>,
+    user: TenantUser,
+    Json(rules): Json<TerritoryRules>,
+) -> Result<Json<Value>, StatusCode> {
+    let TenantUser { tenant_context, request_context, .. } = user.staff_only()?;
+    let service = state.territory_service(&tenant_context, &request_context);
+
+    match service.preview_matches(&rules).await {
+        Ok(customer_ids) => Ok(Json(json!({
+            "success": true,
+            "count": customer_ids.len(),
+            "customer_ids": customer_ids
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to preview territory matches: {}", e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// How many customers a re-assignment would move between which assignees
+async fn preview_reassignment(
+    State(state): State<AppState>,
+    user: TenantUser,
+) -> Result<Json<Value>, StatusCode> {
+    let TenantUser { tenant_context, request_context, .. } = user.staff_only()?;
+    let service = state.territory_service(&tenant_context, &request_context);
+
+    match service.preview_reassignment().await {
+        Ok(plan) => Ok(Json(plan_json(&plan))),
+        Err(e) => {
+            tracing::error!("Failed to preview re-assignment: {}", e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Re-evaluate the territories for every customer
+async fn reassign(
+    State(state): State<AppState>,
+    user: TenantUser,
+) -> Result<Json<Value>, StatusCode> {
+    let TenantUser { tenant_context, request_context, .. } = user.staff_only()?;
+    let service = state.territory_service(&tenant_context, &request_context);
+
+    match service.reassign().await {
+        Ok(plan) => Ok(Json(plan_json(&plan))),
+        Err(e) => {
+            tracing::error!("Failed to re-assign customers: {}", e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Derived and overridden assignees of a customer
+async fn get_assignment(
+    State(state): State<AppState>,
+    Path(customer_id): Path<Uuid>,
+    user: TenantUser,
+) -> Result<Json<Value>, StatusCode> {
+    let TenantUser { tenant_context, request_context, .. } = user.staff_only()?;
+    let service = state.territory_service(&tenant_context, &request_context);
+
+    match service.get_assignment(customer_id).await {
+        Ok(assignment) => Ok(Json(json!({
+            "success": true,
+            "customer_id": customer_id,
+            "assignment": assignment
+        }))),
+        Err(e) => Err(error_status(&e)),
+    }
+}
+
+/// Set or clear the explicit assignees of a customer
+async fn set_overrides(
+    State(state): State<AppState>,
+    Path(customer_id): Path<Uuid>,
+    user: TenantUser,
+    Json(request): Json<AssignmentOverrideRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    let TenantUser { tenant_context, request_context, .. } = user.staff_only()?;
+    let service = state.territory_service(&tenant_context, &request_context);
+
+    match service.set_overrides(customer_id, request).await {
+        Ok(assignment) => Ok(Json(json!({
+            "success": true,
+            "customer_id": customer_id,
+            "assignment": assignment
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to assign customer {}: {}", customer_id, e);
+            Err(error_status(&e))
+        }
+    }
+}
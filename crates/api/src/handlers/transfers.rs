@@ -0,0 +1,220 @@
+//! Stock transfer handlers
+//!
+//! Transfers between locations reserve their stock on creation. Those matching
+//! a tenant approval rule wait in `pending_approval` until a user holding
+//! `inventory:approve_transfers`, other than the creator, approves or rejects
+//! them. A transfer that would overfill the target location or one of its
+//! bins is refused unless created with `override_capacity` by a user holding
+//! `inventory:capacity_override`. Every call acts as the authenticated user.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json,
+    routing::{get, post, Router},
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::api_middleware::tenant_context::TenantUser;
+use crate::state::AppState;
+use erp_master_data::inventory::{CreateStockTransferRequest, TransferApprovalRule, TransferStatus};
+use erp_master_data::MasterDataError;
+
+/// `status` filter value for transfers the caller may approve or reject
+pub const AWAITING_MY_APPROVAL: &str = "awaiting_my_approval";
+
+#[derive(Debug, Deserialize)]
+pub struct TransferListParams {
+    /// A transfer status such as `pending_approval`, or `awaiting_my_approval`
+    pub status: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TransferDecisionRequest {
+    /// Required when rejecting
+    pub comment: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ApprovalRulesRequest {
+    pub rules: Vec<TransferApprovalRule>,
+}
+
+/// Create stock transfer routes; they need an authenticated user
+pub fn transfer_routes() -> Router<AppState> {
+    Router::new()
+        .route("/", post(create_transfer).get(list_transfers))
+        .route("/approval-rules", get(get_approval_rules).put(set_approval_rules))
+        .route("/:id", get(get_transfer))
+        .route("/:id/approve", post(approve_transfer))
+        .route("/:id/reject", post(reject_transfer))
+}
+
+fn error_status(e: &MasterDataError) -> StatusCode {
+    match e {
+        MasterDataError::NotFoundError(_) => StatusCode::NOT_FOUND,
+        MasterDataError::ValidationError { .. } => StatusCode::BAD_REQUEST,
+        MasterDataError::PermissionDenied { .. } => StatusCode::FORBIDDEN,
+        MasterDataError::InvalidTransferTransition { .. } | MasterDataError::LocationCapacityExceeded { .. } => {
+            StatusCode::CONFLICT
+        }
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// Reserve the stock and start the transfer, held for approval if a rule matches
+async fn create_transfer(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+    Json(request): Json<CreateStockTransferRequest>,
+) -> Result<(StatusCode, Json<Value>), StatusCode> {
+    let service = state.stock_transfer_service(&tenant_context, &request_context);
+
+    match service.create_transfer(request).await {
+        Ok(record) => {
+            state.transfer_approvals.transfer_pending(&record).await;
+            Ok((StatusCode::CREATED, Json(json!({
+                "success": true,
+                "transfer": record
+            }))))
+        }
+        Err(e) => {
+            tracing::error!("Failed to create stock transfer: {}", e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Transfers, most recently requested first, optionally filtered by status
+async fn list_transfers(
+    State(state): State<AppState>,
+    Query(params): Query<TransferListParams>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.stock_transfer_service(&tenant_context, &request_context);
+    let (status, awaiting_my_approval) = match params.status.as_deref() {
+        None => (None, false),
+        Some(AWAITING_MY_APPROVAL) => (None, true),
+        Some(status) => (Some(TransferStatus::parse(status).ok_or(StatusCode::BAD_REQUEST)?), false),
+    };
+
+    match service.list_transfers(status, awaiting_my_approval).await {
+        Ok(transfers) => Ok(Json(json!({
+            "success": true,
+            "transfers": transfers
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to list stock transfers: {}", e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// A transfer with its approval, if it needed one
+async fn get_transfer(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.stock_transfer_service(&tenant_context, &request_context);
+
+    match service.get_transfer(id).await {
+        Ok(record) => Ok(Json(json!({
+            "success": true,
+            "transfer": record
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to get stock transfer {}: {}", id, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Release a transfer waiting for approval for picking
+async fn approve_transfer(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+    Json(request): Json<TransferDecisionRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.stock_transfer_service(&tenant_context, &request_context);
+
+    match service.approve_transfer(id, request.comment).await {
+        Ok(record) => {
+            state.transfer_approvals.transfer_decided(&record).await;
+            Ok(Json(json!({
+                "success": true,
+                "transfer": record
+            })))
+        }
+        Err(e) => {
+            tracing::error!("Failed to approve stock transfer {}: {}", id, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Reject a transfer waiting for approval and release its reserved stock
+async fn reject_transfer(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+    Json(request): Json<TransferDecisionRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.stock_transfer_service(&tenant_context, &request_context);
+
+    match service.reject_transfer(id, request.comment).await {
+        Ok(record) => {
+            state.transfer_approvals.transfer_decided(&record).await;
+            Ok(Json(json!({
+                "success": true,
+                "transfer": record
+            })))
+        }
+        Err(e) => {
+            tracing::error!("Failed to reject stock transfer {}: {}", id, e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// The tenant's approval rules; no rules means no transfer needs approval
+async fn get_approval_rules(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.stock_transfer_service(&tenant_context, &request_context);
+
+    match service.get_approval_rules().await {
+        Ok(rules) => Ok(Json(json!({
+            "success": true,
+            "rules": rules
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to get transfer approval rules: {}", e);
+            Err(error_status(&e))
+        }
+    }
+}
+
+/// Replace the tenant's approval rules; transfers already created keep their status
+async fn set_approval_rules(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+    Json(request): Json<ApprovalRulesRequest>,
+) -> Result<Json<Value>, StatusCode> {
+    let service = state.stock_transfer_service(&tenant_context, &request_context);
+
+    match service.set_approval_rules(request.rules).await {
+        Ok(rules) => Ok(Json(json!({
+            "success": true,
+            "rules": rules
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to save transfer approval rules: {}", e);
+            Err(error_status(&e))
+        }
+    }
+}
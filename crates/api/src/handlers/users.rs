@@ -5,16 +5,18 @@
 use axum::{
     extract::{State, Path, Query, Extension},
     http::StatusCode,
-    response::Json,
+    response::{IntoResponse, Json, Response},
     routing::{get, post, put, delete, Router},
 };
 use serde::Deserialize;
 use serde_json::{json, Value};
 use uuid::Uuid;
 
+use crate::handlers::pagination::PaginatedResponse;
 use crate::state::AppState;
-use erp_core::TenantContext;
-use erp_auth::dto::{InviteUserRequest as AuthInviteUserRequest, UpdateUserRequest as AuthUpdateUserRequest};
+use erp_core::{RequestContext, TenantContext};
+use erp_auth::dto::{InviteUserRequest as AuthInviteUserRequest, UpdateUserRequest as AuthUpdateUserRequest, UserResponse};
+use erp_auth::models::AccountState;
 
 #[derive(Debug, Deserialize)]
 pub struct PaginationParams {
@@ -22,6 +24,8 @@ pub struct PaginationParams {
     pub page: u32,
     #[serde(default = "default_limit")]
     pub limit: u32,
+    /// Only users in this state: `active`, `deactivated`, `soft_deleted` or `locked`
+    pub state: Option<AccountState>,
 }
 
 fn default_page() -> u32 { 1 }
@@ -59,35 +63,55 @@ pub fn user_routes() -> Router<AppState> {
         .route("/:id", get(get_user))
         .route("/:id", put(update_user))
         .route("/:id", delete(delete_user))
+        .route("/:id/reactivate", post(reactivate_user))
+        .route("/:id/invitation", post(resend_invitation))
         .route("/invite", post(invite_user))
 }
 
 /// List all users
-async fn list_users(
+#[utoipa::path(
+    get,
+    path = "/api/v1/users",
+    params(
+        ("X-Tenant-Id" = String, Header, description = "Tenant ID"),
+        ("page" = Option<u32>, Query, description = "1-based page, defaults to 1"),
+        ("limit" = Option<u32>, Query, description = "Users per page, defaults to 20, at most 100"),
+        ("state" = Option<AccountState>, Query, description = "Only users in this account state")
+    ),
+    responses(
+        (status = 200, description = "One page of users", body = PaginatedResponse<UserResponse>),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Insufficient permissions"),
+    ),
+    tag = "users",
+    security(
+        ("bearer_auth" = ["user:read"])
+    )
+)]
+pub async fn list_users(
     State(state): State<AppState>,
     Query(params): Query<PaginationParams>,
     Extension(tenant_context): Extension<TenantContext>,
-) -> Result<Json<Value>, StatusCode> {
-    match state.auth_service.list_users(&tenant_context, params.limit, (params.page - 1) * params.limit).await {
-        Ok(users) => {
-            Ok(Json(json!({
-                "success": true,
-                "users": users,
-                "pagination": {
-                    "page": params.page,
-                    "limit": params.limit,
-                    "total": users.len(),
-                    "total_pages": (users.len() as f64 / params.limit as f64).ceil() as u32
-                }
-            })))
-        }
+) -> Result<Response, StatusCode> {
+    // The service caps pages at 100 users
+    let per_page = params.limit.clamp(1, 100);
+    let page = params.page.max(1);
+
+    let listing = async {
+        let users = state.auth_service.list_users(&tenant_context, page - 1, per_page, params.state).await?;
+        let total = state.auth_service.count_users(&tenant_context, params.state).await?;
+        Ok::<_, erp_core::Error>(PaginatedResponse::new(users, total, page, per_page))
+    };
+
+    match listing.await {
+        Ok(users) => Ok(users.into_response()),
         Err(e) => {
             tracing::error!("Failed to list users: {}", e);
             Ok(Json(json!({
                 "success": false,
                 "error": "Failed to retrieve users",
                 "message": e.to_string()
-            })))
+            })).into_response())
         }
     }
 }
@@ -189,10 +213,42 @@ async fn delete_user(
     }
 }
 
+/// Reactivate a deactivated or soft-deleted user
+async fn reactivate_user(
+    State(state): State<AppState>,
+    Path(user_id): Path<Uuid>,
+    Extension(tenant_context): Extension<TenantContext>,
+) -> Result<Json<Value>, StatusCode> {
+    match state.auth_service.reactivate_user(&tenant_context, user_id).await {
+        Ok(user) => {
+            let message = if user.email_verified {
+                "User reactivated successfully"
+            } else {
+                "User reactivated; an invitation or verification email was sent"
+            };
+            Ok(Json(json!({
+                "success": true,
+                "user": user,
+                "message": message
+            })))
+        }
+        Err(e) => {
+            tracing::error!("Failed to reactivate user {}: {}", user_id, e);
+            Ok(Json(json!({
+                "success": false,
+                "error": "Failed to reactivate user",
+                "code": e.code,
+                "message": e.to_string()
+            })))
+        }
+    }
+}
+
 /// Invite a new user
 async fn invite_user(
     State(state): State<AppState>,
     Extension(tenant_context): Extension<TenantContext>,
+    request_context: Option<RequestContext>,
     Json(payload): Json<InviteUserRequest>,
 ) -> Result<Json<Value>, StatusCode> {
     // Basic validation
@@ -211,7 +267,7 @@ async fn invite_user(
         role_ids: payload.role_ids,
     };
 
-    match state.auth_service.invite_user(&tenant_context, auth_request).await {
+    match state.auth_service.invite_user(&tenant_context, auth_request, request_context.and_then(|context| context.user_id)).await {
         Ok(user) => {
             Ok(Json(json!({
                 "success": true,
@@ -228,4 +284,28 @@ async fn invite_user(
             })))
         }
     }
-}
\ No newline at end of file
+}
+
+/// Send a user who has not accepted their invitation a new one; the previous link stops working
+async fn resend_invitation(
+    State(state): State<AppState>,
+    Path(user_id): Path<Uuid>,
+    Extension(tenant_context): Extension<TenantContext>,
+    request_context: Option<RequestContext>,
+) -> Result<Json<Value>, StatusCode> {
+    match state.auth_service.resend_invitation(&tenant_context, user_id, request_context.and_then(|context| context.user_id)).await {
+        Ok(()) => Ok(Json(json!({
+            "success": true,
+            "message": "User invitation sent successfully"
+        }))),
+        Err(e) => {
+            tracing::error!("Failed to resend invitation to user {}: {}", user_id, e);
+            Ok(Json(json!({
+                "success": false,
+                "error": "Failed to resend invitation",
+                "code": e.code,
+                "message": e.to_string()
+            })))
+        }
+    }
+}
@@ -0,0 +1,198 @@
+//! Tenant webhook handlers
+//!
+//! Subscriptions of the signed-in user's tenant to customer, inventory and
+//! auth events, the deliveries made to them across sources, and the catalog
+//! of event types with the JSON Schema of each envelope. A subscription's
+//! secret is returned only when it is created or rotated. See
+//! [`crate::webhooks`] for how events are queued and delivered. They need
+//! `settings:write`.
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::Json,
+    routing::{get, post, put, Router},
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::api_middleware::tenant_context::TenantUser;
+use crate::state::AppState;
+use crate::webhooks::{envelope_schema, DeliveryQuery, DeliveryStatus, SubscriptionRequest, WebhookSource, EVENT_TYPES};
+use erp_core::{Error, ErrorCode};
+
+#[derive(Debug, Deserialize)]
+pub struct DeliveryParams {
+    pub source: Option<String>,
+    /// Event type within the source, e.g. `stockout`
+    #[serde(rename = "type")]
+    pub event_type: Option<String>,
+    /// `pending`, `delivered` or `failed`
+    pub status: Option<String>,
+    pub subscription_id: Option<Uuid>,
+    /// `next_before` of the previous page
+    pub before: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+/// Webhook subscriptions, deliveries and event types; they need `settings:write`
+pub fn webhook_routes() -> Router<AppState> {
+    Router::new()
+        .route("/subscriptions", get(list_subscriptions).post(create_subscription))
+        .route("/subscriptions/:subscription_id", put(update_subscription).delete(delete_subscription))
+        .route("/subscriptions/:subscription_id/rotate-secret", post(rotate_secret))
+        .route("/deliveries", get(list_deliveries))
+        .route("/event-types", get(event_types))
+}
+
+fn error_status(e: &Error) -> StatusCode {
+    match e.code {
+        ErrorCode::ValidationFailed => StatusCode::BAD_REQUEST,
+        ErrorCode::NotFound => StatusCode::NOT_FOUND,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+fn failure(e: Error, action: &str) -> (StatusCode, Json<Value>) {
+    let status = error_status(&e);
+    if status == StatusCode::INTERNAL_SERVER_ERROR {
+        tracing::error!("Failed to {}: {}", action, e);
+    }
+    (status, Json(json!({ "success": false, "error": e.message })))
+}
+
+fn bad_request(message: String) -> (StatusCode, Json<Value>) {
+    (StatusCode::BAD_REQUEST, Json(json!({ "success": false, "error": message })))
+}
+
+/// The tenant's subscriptions, without their secrets
+async fn list_subscriptions(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, .. }: TenantUser,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let subscriptions = state
+        .webhooks
+        .subscriptions(tenant_context.tenant_id.0)
+        .await
+        .map_err(|e| failure(e, "list webhook subscriptions"))?;
+    Ok(Json(json!({ "success": true, "subscriptions": subscriptions })))
+}
+
+/// A new subscription; the response carries its signing secret, shown this once
+async fn create_subscription(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, request_context, .. }: TenantUser,
+    Json(request): Json<SubscriptionRequest>,
+) -> Result<(StatusCode, Json<Value>), (StatusCode, Json<Value>)> {
+    let subscription = state
+        .webhooks
+        .create_subscription(tenant_context.tenant_id.0, request_context.user_id, &request)
+        .await
+        .map_err(|e| failure(e, "create webhook subscription"))?;
+    Ok((
+        StatusCode::CREATED,
+        Json(json!({ "success": true, "subscription": subscription, "secret": subscription.secret })),
+    ))
+}
+
+async fn update_subscription(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, .. }: TenantUser,
+    Path(subscription_id): Path<Uuid>,
+    Json(request): Json<SubscriptionRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let subscription = state
+        .webhooks
+        .update_subscription(tenant_context.tenant_id.0, subscription_id, &request)
+        .await
+        .map_err(|e| failure(e, "update webhook subscription"))?;
+    Ok(Json(json!({ "success": true, "subscription": subscription })))
+}
+
+/// A new signing secret, shown this once
+async fn rotate_secret(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, .. }: TenantUser,
+    Path(subscription_id): Path<Uuid>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let subscription = state
+        .webhooks
+        .rotate_secret(tenant_context.tenant_id.0, subscription_id)
+        .await
+        .map_err(|e| failure(e, "rotate webhook secret"))?;
+    Ok(Json(json!({ "success": true, "subscription": subscription, "secret": subscription.secret })))
+}
+
+/// Remove a subscription with its deliveries
+async fn delete_subscription(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, .. }: TenantUser,
+    Path(subscription_id): Path<Uuid>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    state
+        .webhooks
+        .delete_subscription(tenant_context.tenant_id.0, subscription_id)
+        .await
+        .map_err(|e| failure(e, "delete webhook subscription"))?;
+    Ok(Json(json!({ "success": true })))
+}
+
+/// Recent deliveries across sources, newest first
+async fn list_deliveries(
+    State(state): State<AppState>,
+    TenantUser { tenant_context, .. }: TenantUser,
+    Query(params): Query<DeliveryParams>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let source = match params.source.as_deref() {
+        Some(source) => {
+            Some(WebhookSource::parse(source).ok_or_else(|| bad_request(format!("Unknown event source '{}'", source)))?)
+        }
+        None => None,
+    };
+    let status = match params.status.as_deref() {
+        Some(status) => {
+            Some(DeliveryStatus::parse(status).ok_or_else(|| bad_request(format!("Unknown delivery status '{}'", status)))?)
+        }
+        None => None,
+    };
+    let query = DeliveryQuery {
+        source,
+        event_type: params.event_type.clone(),
+        status,
+        subscription_id: params.subscription_id,
+        before_id: params.before,
+    };
+
+    let page = state
+        .webhooks
+        .deliveries(tenant_context.tenant_id.0, &query, params.limit)
+        .await
+        .map_err(|e| failure(e, "list webhook deliveries"))?;
+    Ok(Json(json!({
+        "success": true,
+        "deliveries": page.deliveries,
+        "next_before": page.next_before,
+        "has_more": page.has_more
+    })))
+}
+
+/// Every event type subscriptions can take, with the schema and an example of its envelope
+async fn event_types(
+    _: TenantUser,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let event_types: Vec<Value> = EVENT_TYPES
+        .iter()
+        .map(|doc| {
+            json!({
+                "name": doc.qualified_name(),
+                "source": doc.source,
+                "type": doc.event_type,
+                "description": doc.description,
+                "schema": envelope_schema(doc),
+                "example_data": (doc.example)(),
+            })
+        })
+        .collect();
+    Ok(Json(json!({ "success": true, "event_types": event_types })))
+}
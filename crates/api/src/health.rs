@@ -18,9 +18,16 @@
 //! ### Readiness Check (`/ready`)  
 //! - **Purpose**: Indicates if the service can handle requests
 //! - **Dependencies**: Tests database and Redis connectivity
+//! - **Soft checks**: Job queue health, reported without affecting readiness
 //! - **Response**: 200 OK if ready, 503 Service Unavailable if not
 //! - **Use case**: Kubernetes readiness probes, deployment validation
 //! 
+//! ### Metrics (`metrics.path`, default `/metrics`)
+//! - **Purpose**: Prometheus scrape target, mounted with `metrics.enabled`
+//! - **Response**: The metrics registry in the text exposition format
+//! - **Placement**: Outside the auth middleware; on `server.metrics_port`
+//!   instead of the API port when that is set
+//!
 //! ## Integration Examples
 //! 
 //! ### Docker Health Check
@@ -48,14 +55,14 @@
 
 use axum::{
     extract::State,
-    http::StatusCode,
+    http::{header, StatusCode},
     response::IntoResponse,
     Json,
 };
 use serde_json::json;
 use tracing::error;
 
-use crate::state::AppState;
+use crate::{build_info::build_info, state::AppState};
 
 /// Basic health check endpoint for liveness monitoring.
 /// 
@@ -70,7 +77,15 @@ use crate::state::AppState;
 /// {
 ///   "status": "healthy",
 ///   "service": "erp-api", 
-///   "version": "0.1.0"
+///   "version": "0.1.0",
+///   "build": {
+///     "version": "0.1.0",
+///     "commit": "3f2c1e9a...",
+///     "commit_short": "3f2c1e9",
+///     "dirty": false,
+///     "built_at": "2026-10-01T12:00:00+00:00",
+///     "rustc_version": "rustc 1.80.0 (051478957 2024-07-21)"
+///   }
 /// }
 /// ```
 /// 
@@ -97,6 +112,7 @@ use crate::state::AppState;
     responses(
         (status = 200, description = "Service is healthy", body = Object)
     ),
+    security(()),
     tag = "health"
 )]
 pub async fn health_check() -> impl IntoResponse {
@@ -104,6 +120,7 @@ pub async fn health_check() -> impl IntoResponse {
         "status": "healthy",
         "service": "erp-api",
         "version": env!("CARGO_PKG_VERSION"),
+        "build": build_info(),
     }))
 }
 
@@ -115,6 +132,10 @@ pub async fn health_check() -> impl IntoResponse {
 /// - **Database connectivity**: PostgreSQL connection and query capability
 /// - **Cache connectivity**: Redis connection and command execution
 /// 
+/// Job queue health (see [`crate::job_health`]) is a soft check: an unhealthy
+/// queue is reported under `soft_checks` and `jobs` but the service stays ready,
+/// since requests are still served while background jobs are stuck.
+/// 
 /// # Response Format
 /// 
 /// **Ready State (200 OK):**
@@ -124,7 +145,23 @@ pub async fn health_check() -> impl IntoResponse {
 ///   "checks": {
 ///     "database": true,
 ///     "redis": true
-///   }
+///   },
+///   "soft_checks": {
+///     "jobs": false
+///   },
+///   "jobs": [
+///     {
+///       "queue": "auth_jobs",
+///       "healthy": false,
+///       "depth": 42,
+///       "oldest_job_age_seconds": 610,
+///       "heartbeat_age_seconds": 604,
+///       "consumers": 0,
+///       "in_flight": 0,
+///       "issues": [{ "issue": "stale_heartbeat", "age_seconds": 604 }],
+///       "checked_at": "2026-10-17T08:00:00Z"
+///     }
+///   ]
 /// }
 /// ```
 /// 
@@ -180,6 +217,7 @@ pub async fn health_check() -> impl IntoResponse {
         (status = 200, description = "Service is ready", body = Object),
         (status = 503, description = "Service is not ready", body = Object)
     ),
+    security(()),
     tag = "health"
 )]
 pub async fn readiness_check(State(state): State<AppState>) -> impl IntoResponse {
@@ -209,6 +247,10 @@ pub async fn readiness_check(State(state): State<AppState>) -> impl IntoResponse
 
     let is_ready = db_healthy && redis_healthy;
 
+    // Soft dependency: reported, but does not make the service unready
+    let jobs = state.job_health.latest().await;
+    let jobs_healthy = jobs.iter().all(|queue| queue.healthy);
+
     let status = if is_ready {
         StatusCode::OK
     } else {
@@ -222,7 +264,30 @@ pub async fn readiness_check(State(state): State<AppState>) -> impl IntoResponse
             "checks": {
                 "database": db_healthy,
                 "redis": redis_healthy,
-            }
+            },
+            "soft_checks": {
+                "jobs": jobs_healthy,
+            },
+            "jobs": jobs,
         })),
     )
-}
\ No newline at end of file
+}
+/// Prometheus scrape endpoint.
+///
+/// Refreshes the gauges read at scrape time, the checked-out database
+/// connections, then renders the whole registry in the text exposition format.
+///
+/// ```bash
+/// curl http://localhost:9090/metrics
+/// ```
+pub async fn metrics(State(state): State<AppState>) -> impl IntoResponse {
+    state
+        .http_metrics
+        .db_pool_active_connections
+        .set(state.db.active_connections() as i64);
+
+    (
+        [(header::CONTENT_TYPE, prometheus::TEXT_FORMAT)],
+        state.metrics.metrics_text(),
+    )
+}
@@ -0,0 +1,674 @@
+//! # Idempotent Create Requests
+//!
+//! A client retrying a create request that timed out cannot know whether the
+//! first attempt created the entity. Create endpoints layered with
+//! [`idempotency_middleware`] accept an `Idempotency-Key` header of 1 to 255
+//! visible ASCII characters. The first request with a key claims it in
+//! `public.idempotency_keys` for its tenant and endpoint; once it answered
+//! `201 Created`, its status, body and `Location` are kept for
+//! `idempotency.ttl_hours` and every request reusing the key gets them back
+//! with `Idempotent-Replayed: true` instead of creating another entity.
+//!
+//! - Reusing a key with a different body (compared as canonical JSON) is
+//!   refused with `409 Conflict`.
+//! - A request reusing the key of one still running waits up to
+//!   `idempotency.wait_ms` for its response, then gets `409` with
+//!   `Retry-After`.
+//! - A request that did not create anything releases its key, so the client
+//!   can correct it and retry with the same key. A claim whose request never
+//!   finished is taken over after `idempotency.lease_secs`.
+//! - Keys are per tenant; two tenants may use the same key.
+//!
+//! Requests without the header are not affected. Expired keys are deleted
+//! every few minutes.
+
+use async_trait::async_trait;
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use erp_core::{IdempotencyConfig, Result, TenantContext};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use sqlx::{PgPool, Row};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// Header naming the key of a create request
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+/// Header marking a response replayed for a reused key
+pub const IDEMPOTENT_REPLAYED_HEADER: &str = "idempotent-replayed";
+
+/// Scope of `POST /api/v1/customers`
+pub const CUSTOMERS_SCOPE: &str = "customers";
+
+/// Longest key accepted
+const MAX_KEY_LENGTH: usize = 255;
+
+/// Largest request body hashed; create requests are far smaller
+const MAX_REQUEST_BYTES: usize = 2 * 1024 * 1024;
+
+/// A key as claimed by one tenant for one endpoint
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct IdempotencyKey {
+    pub tenant_id: Uuid,
+    pub scope: String,
+    pub key: String,
+}
+
+/// The `201 Created` response replayed for a key
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredResponse {
+    pub status: u16,
+    pub content_type: Option<String>,
+    pub location: Option<String>,
+    /// The created entity, taken from the last segment of `location`
+    pub entity_id: Option<Uuid>,
+    pub body: Vec<u8>,
+}
+
+/// A key claimed by another request; `response` is `None` while it still runs
+#[derive(Debug, Clone)]
+pub struct IdempotencyRecord {
+    pub request_hash: String,
+    pub response: Option<StoredResponse>,
+    pub locked_until: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Result of claiming a key
+#[derive(Debug)]
+pub enum Claim {
+    /// The request may run; the key is held until `locked_until`
+    Claimed,
+    /// A live claim or response holds the key
+    Existing(IdempotencyRecord),
+}
+
+/// What to do with a request carrying a key
+#[derive(Debug)]
+pub enum Outcome {
+    Proceed,
+    Replay(StoredResponse),
+    /// The key was used for a different request
+    Mismatch,
+    /// The request holding the key did not finish in time
+    InProgress,
+}
+
+/// Storage of idempotency keys
+#[async_trait]
+pub trait IdempotencyStore: Send + Sync {
+    /// Claim the key unless a live claim or an unexpired response holds it at `now`
+    async fn claim(
+        &self,
+        key: &IdempotencyKey,
+        request_hash: &str,
+        now: DateTime<Utc>,
+        locked_until: DateTime<Utc>,
+        expires_at: DateTime<Utc>,
+    ) -> Result<Claim>;
+
+    /// Keep the response of the claiming request until `expires_at`
+    async fn complete(&self, key: &IdempotencyKey, response: &StoredResponse, expires_at: DateTime<Utc>) -> Result<()>;
+
+    /// Give up a claim whose request created nothing
+    async fn release(&self, key: &IdempotencyKey) -> Result<()>;
+
+    async fn delete_expired(&self, now: DateTime<Utc>) -> Result<u64>;
+}
+
+pub struct PostgresIdempotencyStore {
+    pool: PgPool,
+}
+
+impl PostgresIdempotencyStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl IdempotencyStore for PostgresIdempotencyStore {
+    async fn claim(
+        &self,
+        key: &IdempotencyKey,
+        request_hash: &str,
+        now: DateTime<Utc>,
+        locked_until: DateTime<Utc>,
+        expires_at: DateTime<Utc>,
+    ) -> Result<Claim> {
+        // The insert and the takeover of an expired or abandoned key are one
+        // statement, so of two racing requests only one gets the row back
+        let claimed = sqlx::query(
+            "INSERT INTO public.idempotency_keys \
+             (tenant_id, scope, idempotency_key, request_hash, state, created_at, locked_until, expires_at) \
+             VALUES ($1, $2, $3, $4, 'pending', $5, $6, $7) \
+             ON CONFLICT (tenant_id, scope, idempotency_key) DO UPDATE \
+             SET request_hash = EXCLUDED.request_hash, state = 'pending', response_status = NULL, \
+                 content_type = NULL, location = NULL, entity_id = NULL, response_body = NULL, \
+                 created_at = EXCLUDED.created_at, locked_until = EXCLUDED.locked_until, \
+                 expires_at = EXCLUDED.expires_at \
+             WHERE public.idempotency_keys.expires_at <= $5 \
+                OR (public.idempotency_keys.state = 'pending' AND public.idempotency_keys.locked_until <= $5) \
+             RETURNING idempotency_key",
+        )
+        .bind(key.tenant_id)
+        .bind(&key.scope)
+        .bind(&key.key)
+        .bind(request_hash)
+        .bind(now)
+        .bind(locked_until)
+        .bind(expires_at)
+        .fetch_optional(&self.pool)
+        .await?;
+        if claimed.is_some() {
+            return Ok(Claim::Claimed);
+        }
+
+        let row = sqlx::query(
+            "SELECT request_hash, state, response_status, content_type, location, entity_id, response_body, \
+                    locked_until, expires_at \
+             FROM public.idempotency_keys WHERE tenant_id = $1 AND scope = $2 AND idempotency_key = $3",
+        )
+        .bind(key.tenant_id)
+        .bind(&key.scope)
+        .bind(&key.key)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        // Released between the two statements: the key is free again
+        let Some(row) = row else {
+            return self.claim(key, request_hash, now, locked_until, expires_at).await;
+        };
+        let state: String = row.try_get("state")?;
+        let response = if state == "completed" {
+            let status: Option<i32> = row.try_get("response_status")?;
+            let body: Option<Vec<u8>> = row.try_get("response_body")?;
+            Some(StoredResponse {
+                status: status.map(|status| status as u16).unwrap_or(201),
+                content_type: row.try_get("content_type")?,
+                location: row.try_get("location")?,
+                entity_id: row.try_get("entity_id")?,
+                body: body.unwrap_or_default(),
+            })
+        } else {
+            None
+        };
+        Ok(Claim::Existing(IdempotencyRecord {
+            request_hash: row.try_get("request_hash")?,
+            response,
+            locked_until: row.try_get("locked_until")?,
+            expires_at: row.try_get("expires_at")?,
+        }))
+    }
+
+    async fn complete(&self, key: &IdempotencyKey, response: &StoredResponse, expires_at: DateTime<Utc>) -> Result<()> {
+        sqlx::query(
+            "UPDATE public.idempotency_keys \
+             SET state = 'completed', response_status = $4, content_type = $5, location = $6, entity_id = $7, \
+                 response_body = $8, expires_at = $9 \
+             WHERE tenant_id = $1 AND scope = $2 AND idempotency_key = $3",
+        )
+        .bind(key.tenant_id)
+        .bind(&key.scope)
+        .bind(&key.key)
+        .bind(response.status as i32)
+        .bind(&response.content_type)
+        .bind(&response.location)
+        .bind(response.entity_id)
+        .bind(&response.body)
+        .bind(expires_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn release(&self, key: &IdempotencyKey) -> Result<()> {
+        sqlx::query(
+            "DELETE FROM public.idempotency_keys \
+             WHERE tenant_id = $1 AND scope = $2 AND idempotency_key = $3 AND state = 'pending'",
+        )
+        .bind(key.tenant_id)
+        .bind(&key.scope)
+        .bind(&key.key)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn delete_expired(&self, now: DateTime<Utc>) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM public.idempotency_keys WHERE expires_at <= $1")
+            .bind(now)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+}
+
+/// Claims, replays and expiry of idempotency keys
+pub struct Idempotency {
+    config: IdempotencyConfig,
+    store: Arc<dyn IdempotencyStore>,
+}
+
+impl Idempotency {
+    pub fn new(config: IdempotencyConfig, store: Arc<dyn IdempotencyStore>) -> Self {
+        Self { config, store }
+    }
+
+    /// Middleware state for the create endpoint of `scope`
+    pub fn endpoint(self: &Arc<Self>, scope: &'static str) -> IdempotentEndpoint {
+        IdempotentEndpoint {
+            scope,
+            idempotency: self.clone(),
+        }
+    }
+
+    fn ttl(&self) -> ChronoDuration {
+        ChronoDuration::hours(self.config.ttl_hours as i64)
+    }
+
+    /// Claim the key for a request, waiting for the one holding it if it still runs
+    pub async fn begin(&self, key: &IdempotencyKey, request_hash: &str) -> Result<Outcome> {
+        let deadline = Instant::now() + Duration::from_millis(self.config.wait_ms);
+        let mut pause = Duration::from_millis(25);
+        loop {
+            let now = Utc::now();
+            let locked_until = now + ChronoDuration::seconds(self.config.lease_secs as i64);
+            let record = match self.store.claim(key, request_hash, now, locked_until, now + self.ttl()).await? {
+                Claim::Claimed => return Ok(Outcome::Proceed),
+                Claim::Existing(record) => record,
+            };
+            if record.request_hash != request_hash {
+                return Ok(Outcome::Mismatch);
+            }
+            if let Some(response) = record.response {
+                return Ok(Outcome::Replay(response));
+            }
+
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                return Ok(Outcome::InProgress);
+            }
+            tokio::time::sleep(pause.min(left)).await;
+            pause = (pause * 2).min(Duration::from_millis(250));
+        }
+    }
+
+    pub async fn complete(&self, key: &IdempotencyKey, response: &StoredResponse) -> Result<()> {
+        self.store.complete(key, response, Utc::now() + self.ttl()).await
+    }
+
+    pub async fn release(&self, key: &IdempotencyKey) -> Result<()> {
+        self.store.release(key).await
+    }
+
+    /// Delete expired keys every few minutes
+    pub fn spawn(self: &Arc<Self>) -> tokio::task::JoinHandle<()> {
+        let idempotency = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(300));
+            loop {
+                interval.tick().await;
+                match idempotency.store.delete_expired(Utc::now()).await {
+                    Ok(0) => {}
+                    Ok(deleted) => info!("Deleted {} expired idempotency keys", deleted),
+                    Err(e) => warn!("Deleting expired idempotency keys failed: {}", e),
+                }
+            }
+        })
+    }
+}
+
+/// One create endpoint, the state of [`idempotency_middleware`]
+#[derive(Clone)]
+pub struct IdempotentEndpoint {
+    scope: &'static str,
+    idempotency: Arc<Idempotency>,
+}
+
+/// Whether `key` is 1 to 255 visible ASCII characters
+pub fn is_valid_key(key: &str) -> bool {
+    !key.is_empty() && key.len() <= MAX_KEY_LENGTH && key.bytes().all(|b| b.is_ascii_graphic())
+}
+
+/// SHA-256 of the body as canonical JSON, so key order and whitespace do not matter
+pub fn request_hash(body: &[u8]) -> String {
+    let canonical = match serde_json::from_slice::<Value>(body) {
+        Ok(value) => canonical_json(value).to_string().into_bytes(),
+        Err(_) => body.to_vec(),
+    };
+    hex::encode(Sha256::digest(&canonical))
+}
+
+fn canonical_json(value: Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<_> = map.into_iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(&b.0));
+            Value::Object(entries.into_iter().map(|(key, value)| (key, canonical_json(value))).collect())
+        }
+        Value::Array(items) => Value::Array(items.into_iter().map(canonical_json).collect()),
+        other => other,
+    }
+}
+
+fn refuse(status: StatusCode, error: &str) -> Response {
+    (status, Json(json!({ "success": false, "error": error }))).into_response()
+}
+
+fn replay(stored: StoredResponse) -> Response {
+    let status = StatusCode::from_u16(stored.status).unwrap_or(StatusCode::CREATED);
+    let mut response = (status, stored.body).into_response();
+    let headers = response.headers_mut();
+    if let Some(content_type) = stored.content_type.and_then(|v| HeaderValue::from_str(&v).ok()) {
+        headers.insert(header::CONTENT_TYPE, content_type);
+    }
+    if let Some(location) = stored.location.and_then(|v| HeaderValue::from_str(&v).ok()) {
+        headers.insert(header::LOCATION, location);
+    }
+    headers.insert(IDEMPOTENT_REPLAYED_HEADER, HeaderValue::from_static("true"));
+    response
+}
+
+/// Replay the response of a reused key, or run the request and keep its `201` response
+pub async fn idempotency_middleware(State(endpoint): State<IdempotentEndpoint>, req: Request, next: Next) -> Response {
+    let Some(value) = req.headers().get(IDEMPOTENCY_KEY_HEADER) else {
+        return next.run(req).await;
+    };
+    let Some(key) = value.to_str().ok().filter(|key| is_valid_key(key)).map(str::to_string) else {
+        return refuse(StatusCode::BAD_REQUEST, "Idempotency-Key must be 1 to 255 visible ASCII characters");
+    };
+    // Keys belong to a tenant; without one the header is refused rather than ignored
+    let Some(tenant_id) = req.extensions().get::<TenantContext>().map(|ctx| ctx.tenant_id.0) else {
+        return refuse(StatusCode::BAD_REQUEST, "Idempotency-Key needs a tenant");
+    };
+
+    let (parts, body) = req.into_parts();
+    let Ok(body) = axum::body::to_bytes(body, MAX_REQUEST_BYTES).await else {
+        return refuse(StatusCode::PAYLOAD_TOO_LARGE, "Request body too large");
+    };
+    let key = IdempotencyKey {
+        tenant_id,
+        scope: endpoint.scope.to_string(),
+        key,
+    };
+    let idempotency = &endpoint.idempotency;
+    match idempotency.begin(&key, &request_hash(&body)).await {
+        Ok(Outcome::Proceed) => {}
+        Ok(Outcome::Replay(stored)) => return replay(stored),
+        Ok(Outcome::Mismatch) => {
+            return refuse(StatusCode::CONFLICT, "Idempotency-Key was already used for a different request");
+        }
+        Ok(Outcome::InProgress) => {
+            let mut response = refuse(StatusCode::CONFLICT, "A request with this Idempotency-Key is still running");
+            response.headers_mut().insert(header::RETRY_AFTER, HeaderValue::from(1));
+            return response;
+        }
+        // Running the request unguarded could create the entity twice
+        Err(e) => {
+            warn!("Claiming idempotency key for {} failed: {}", key.scope, e);
+            return refuse(StatusCode::SERVICE_UNAVAILABLE, "Idempotency keys are unavailable; try again later");
+        }
+    }
+
+    let response = next.run(Request::from_parts(parts, Body::from(body))).await;
+    if response.status() != StatusCode::CREATED {
+        if let Err(e) = idempotency.release(&key).await {
+            warn!("Releasing idempotency key for {} failed: {}", key.scope, e);
+        }
+        return response;
+    }
+
+    let (parts, body) = response.into_parts();
+    let body = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(body) => body,
+        Err(e) => {
+            warn!("Reading the response for idempotency key of {} failed: {}", key.scope, e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    let header = |name| parts.headers.get(name).and_then(|v| v.to_str().ok()).map(str::to_string);
+    let location = header(header::LOCATION);
+    let stored = StoredResponse {
+        status: parts.status.as_u16(),
+        content_type: header(header::CONTENT_TYPE),
+        entity_id: location.as_deref().and_then(|l| l.rsplit('/').next()).and_then(|id| id.parse().ok()),
+        location,
+        body: body.to_vec(),
+    };
+    if let Err(e) = idempotency.complete(&key, &stored).await {
+        warn!("Storing the response for idempotency key of {} failed: {}", key.scope, e);
+    }
+    Response::from_parts(parts, Body::from(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{extract::Extension, routing::post, Router};
+    use erp_core::TenantId;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+    use tower::ServiceExt;
+
+    #[derive(Default)]
+    struct InMemoryKeys {
+        keys: Mutex<HashMap<IdempotencyKey, IdempotencyRecord>>,
+    }
+
+    impl InMemoryKeys {
+        fn expire_all(&self) {
+            for record in self.keys.lock().unwrap().values_mut() {
+                record.expires_at = Utc::now() - ChronoDuration::seconds(1);
+            }
+        }
+    }
+
+    #[async_trait]
+    impl IdempotencyStore for InMemoryKeys {
+        async fn claim(
+            &self,
+            key: &IdempotencyKey,
+            request_hash: &str,
+            now: DateTime<Utc>,
+            locked_until: DateTime<Utc>,
+            expires_at: DateTime<Utc>,
+        ) -> Result<Claim> {
+            let mut keys = self.keys.lock().unwrap();
+            if let Some(record) = keys.get(key) {
+                let abandoned = record.response.is_none() && record.locked_until <= now;
+                if record.expires_at > now && !abandoned {
+                    return Ok(Claim::Existing(record.clone()));
+                }
+            }
+            keys.insert(
+                key.clone(),
+                IdempotencyRecord {
+                    request_hash: request_hash.to_string(),
+                    response: None,
+                    locked_until,
+                    expires_at,
+                },
+            );
+            Ok(Claim::Claimed)
+        }
+
+        async fn complete(&self, key: &IdempotencyKey, response: &StoredResponse, expires_at: DateTime<Utc>) -> Result<()> {
+            if let Some(record) = self.keys.lock().unwrap().get_mut(key) {
+                record.response = Some(response.clone());
+                record.expires_at = expires_at;
+            }
+            Ok(())
+        }
+
+        async fn release(&self, key: &IdempotencyKey) -> Result<()> {
+            let mut keys = self.keys.lock().unwrap();
+            if keys.get(key).is_some_and(|record| record.response.is_none()) {
+                keys.remove(key);
+            }
+            Ok(())
+        }
+
+        async fn delete_expired(&self, now: DateTime<Utc>) -> Result<u64> {
+            let mut keys = self.keys.lock().unwrap();
+            let before = keys.len();
+            keys.retain(|_, record| record.expires_at > now);
+            Ok((before - keys.len()) as u64)
+        }
+    }
+
+    /// A create endpoint counting the entities it created
+    fn app(store: Arc<InMemoryKeys>, created: Arc<AtomicUsize>, tenant: u128) -> Router {
+        let idempotency = Arc::new(Idempotency::new(IdempotencyConfig::default(), store));
+        let create = post(move |Json(body): Json<Value>| async move {
+            if body["name"].as_str().unwrap_or_default().is_empty() {
+                return (StatusCode::BAD_REQUEST, Json(json!({ "success": false }))).into_response();
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            created.fetch_add(1, Ordering::SeqCst);
+            let id = Uuid::new_v4();
+            let location = format!("/api/v1/customers/{}", id);
+            (StatusCode::CREATED, [(header::LOCATION, location)], Json(json!({ "success": true, "id": id })))
+                .into_response()
+        })
+        .layer(axum::middleware::from_fn_with_state(idempotency.endpoint(CUSTOMERS_SCOPE), idempotency_middleware));
+
+        Router::new().route("/customers", create).layer(Extension(TenantContext {
+            tenant_id: TenantId(Uuid::from_u128(tenant)),
+            schema_name: "tenant_test".to_string(),
+        }))
+    }
+
+    async fn call(app: Router, key: Option<&str>, body: &str) -> Response {
+        let mut request = Request::builder()
+            .method("POST")
+            .uri("/customers")
+            .header(header::CONTENT_TYPE, "application/json");
+        if let Some(key) = key {
+            request = request.header(IDEMPOTENCY_KEY_HEADER, key);
+        }
+        app.oneshot(request.body(Body::from(body.to_string())).unwrap()).await.unwrap()
+    }
+
+    async fn body_json(response: Response) -> Value {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[test]
+    fn test_request_hash_ignores_key_order_and_whitespace() {
+        assert_eq!(
+            request_hash(br#"{"name":"Acme","tags":[{"a":1,"b":2}]}"#),
+            request_hash(br#"{ "tags": [ {"b": 2, "a": 1} ], "name": "Acme" }"#)
+        );
+        assert_ne!(request_hash(br#"{"name":"Acme"}"#), request_hash(br#"{"name":"Globex"}"#));
+        assert!(is_valid_key("order-2024-0001"));
+        assert!(!is_valid_key(""));
+        assert!(!is_valid_key("with space"));
+        assert!(!is_valid_key(&"k".repeat(256)));
+    }
+
+    #[tokio::test]
+    async fn test_retry_after_success_replays_the_response() {
+        let store = Arc::new(InMemoryKeys::default());
+        let created = Arc::new(AtomicUsize::new(0));
+        let app = app(store.clone(), created.clone(), 1);
+
+        let first = call(app.clone(), Some("key-1"), r#"{"name":"Acme"}"#).await;
+        assert_eq!(first.status(), StatusCode::CREATED);
+        let location = first.headers()[header::LOCATION].clone();
+        let first_body = body_json(first).await;
+
+        let retry = call(app.clone(), Some("key-1"), r#"{ "name": "Acme" }"#).await;
+        assert_eq!(retry.status(), StatusCode::CREATED);
+        assert_eq!(retry.headers()[header::LOCATION], location);
+        assert_eq!(retry.headers()[IDEMPOTENT_REPLAYED_HEADER], "true");
+        assert_eq!(body_json(retry).await, first_body);
+        assert_eq!(created.load(Ordering::SeqCst), 1);
+
+        let stored = store.keys.lock().unwrap().values().next().unwrap().clone();
+        assert_eq!(stored.response.unwrap().entity_id.map(|id| json!(id)), Some(first_body["id"].clone()));
+
+        // Without a key every request creates
+        call(app.clone(), None, r#"{"name":"Acme"}"#).await;
+        assert_eq!(created.load(Ordering::SeqCst), 2);
+
+        // Keys are per tenant
+        let other_tenant = self::app(store, created.clone(), 2);
+        let other = call(other_tenant, Some("key-1"), r#"{"name":"Acme"}"#).await;
+        assert!(other.headers().get(IDEMPOTENT_REPLAYED_HEADER).is_none());
+        assert_eq!(created.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_reusing_a_key_for_a_different_request_conflicts() {
+        let created = Arc::new(AtomicUsize::new(0));
+        let app = app(Arc::new(InMemoryKeys::default()), created.clone(), 1);
+
+        call(app.clone(), Some("key-1"), r#"{"name":"Acme"}"#).await;
+        let conflict = call(app.clone(), Some("key-1"), r#"{"name":"Globex"}"#).await;
+        assert_eq!(conflict.status(), StatusCode::CONFLICT);
+        assert_eq!(created.load(Ordering::SeqCst), 1);
+
+        let invalid = call(app, Some("with space"), r#"{"name":"Acme"}"#).await;
+        assert_eq!(invalid.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_requests_with_one_key_create_once() {
+        let created = Arc::new(AtomicUsize::new(0));
+        let app = app(Arc::new(InMemoryKeys::default()), created.clone(), 1);
+
+        let requests: Vec<_> = (0..5)
+            .map(|_| {
+                let app = app.clone();
+                tokio::spawn(async move { call(app, Some("key-1"), r#"{"name":"Acme"}"#).await })
+            })
+            .collect();
+        let mut bodies = Vec::new();
+        for request in requests {
+            let response = request.await.unwrap();
+            assert_eq!(response.status(), StatusCode::CREATED);
+            bodies.push(body_json(response).await);
+        }
+
+        assert_eq!(created.load(Ordering::SeqCst), 1);
+        assert!(bodies.iter().all(|body| body == &bodies[0]));
+    }
+
+    #[tokio::test]
+    async fn test_failed_request_releases_and_expired_key_is_reusable() {
+        let store = Arc::new(InMemoryKeys::default());
+        let created = Arc::new(AtomicUsize::new(0));
+        let app = app(store.clone(), created.clone(), 1);
+
+        // Nothing was created, so the corrected request may reuse the key
+        let invalid = call(app.clone(), Some("key-1"), r#"{"name":""}"#).await;
+        assert_eq!(invalid.status(), StatusCode::BAD_REQUEST);
+        let first = call(app.clone(), Some("key-1"), r#"{"name":"Acme"}"#).await;
+        assert!(first.headers().get(IDEMPOTENT_REPLAYED_HEADER).is_none());
+        assert_eq!(created.load(Ordering::SeqCst), 1);
+
+        let record = store.keys.lock().unwrap().values().next().unwrap().clone();
+        let ttl = record.expires_at - Utc::now();
+        assert!(ttl > ChronoDuration::hours(23) && ttl <= ChronoDuration::hours(24));
+
+        store.expire_all();
+        let later = call(app, Some("key-1"), r#"{"name":"Globex"}"#).await;
+        assert_eq!(later.status(), StatusCode::CREATED);
+        assert!(later.headers().get(IDEMPOTENT_REPLAYED_HEADER).is_none());
+        assert_eq!(created.load(Ordering::SeqCst), 2);
+        assert_eq!(store.delete_expired(Utc::now()).await.unwrap(), 0);
+    }
+}
@@ -0,0 +1,223 @@
+//! # Real-Time Inventory Alerts
+//!
+//! Inventory alerts can be watched live under
+//! `GET /api/v1/inventory/alerts/stream` (a WebSocket upgrade) instead of
+//! polling for them.
+//!
+//! - Every alert the inventory repository creates is published by a
+//!   [`RedisAlertPublisher`] as JSON to the Redis channel of its tenant
+//!   ([`alert_channel`]), so every server instance sees it.
+//! - Each open stream subscribes to the channel of its tenant and forwards
+//!   the alerts that pass its [`SeverityFilter`], given as
+//!   `?severity=high,critical`; without one every alert is forwarded.
+//! - A tenant holds at most `inventory_alerts.max_connections_per_tenant`
+//!   streams at a time ([`InventoryAlertStreams::claim`]); the slot of a
+//!   stream is given back when its connection closes, however it closes.
+
+use async_trait::async_trait;
+use erp_core::InventoryAlertsConfig;
+use erp_master_data::inventory::{AlertSeverity, InventoryAlert, InventoryAlertPublisher};
+use erp_master_data::MasterDataError;
+use redis::aio::{ConnectionManager, PubSub};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use uuid::Uuid;
+
+/// Redis pub/sub channel carrying the inventory alerts of a tenant
+pub fn alert_channel(tenant_id: Uuid) -> String {
+    format!("inventory_alerts:{}", tenant_id)
+}
+
+/// Publishes the alerts of one tenant to its channel
+pub struct RedisAlertPublisher {
+    redis: ConnectionManager,
+    tenant_id: Uuid,
+}
+
+impl RedisAlertPublisher {
+    pub fn new(redis: ConnectionManager, tenant_id: Uuid) -> Self {
+        Self { redis, tenant_id }
+    }
+}
+
+#[async_trait]
+impl InventoryAlertPublisher for RedisAlertPublisher {
+    async fn publish(&self, alert: &InventoryAlert) -> erp_master_data::Result<()> {
+        let payload = serde_json::to_string(alert)?;
+        let mut conn = self.redis.clone();
+        redis::AsyncCommands::publish::<_, _, ()>(&mut conn, alert_channel(self.tenant_id), payload)
+            .await
+            .map_err(|e| MasterDataError::Internal {
+                message: format!("Failed to publish inventory alert: {}", e),
+            })
+    }
+}
+
+/// Severities a stream forwards; empty forwards every alert
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SeverityFilter {
+    severities: Vec<AlertSeverity>,
+}
+
+impl SeverityFilter {
+    /// Parse a comma separated list of severities, in any case; `None` for an unknown one
+    pub fn parse(value: Option<&str>) -> Option<Self> {
+        let severities = value
+            .unwrap_or_default()
+            .split(',')
+            .map(|s| s.trim().to_lowercase())
+            .filter(|s| !s.is_empty())
+            .map(|s| parse_severity(&s))
+            .collect::<Option<Vec<_>>>()?;
+        Some(Self { severities })
+    }
+
+    pub fn matches(&self, alert: &InventoryAlert) -> bool {
+        self.severities.is_empty() || self.severities.contains(&alert.severity)
+    }
+}
+
+fn parse_severity(value: &str) -> Option<AlertSeverity> {
+    match value {
+        "info" => Some(AlertSeverity::Info),
+        "low" => Some(AlertSeverity::Low),
+        "medium" => Some(AlertSeverity::Medium),
+        "high" => Some(AlertSeverity::High),
+        "warning" => Some(AlertSeverity::Warning),
+        "critical" => Some(AlertSeverity::Critical),
+        "emergency" => Some(AlertSeverity::Emergency),
+        _ => None,
+    }
+}
+
+/// Open alert streams per tenant and the Redis client they subscribe with
+pub struct InventoryAlertStreams {
+    client: redis::Client,
+    config: InventoryAlertsConfig,
+    open: Arc<Mutex<HashMap<Uuid, usize>>>,
+}
+
+impl InventoryAlertStreams {
+    pub fn new(client: redis::Client, config: InventoryAlertsConfig) -> Self {
+        Self {
+            client,
+            config,
+            open: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Take one of the tenant's stream slots, or `None` if all are taken
+    pub fn claim(&self, tenant_id: Uuid) -> Option<StreamSlot> {
+        let mut open = self.open.lock().unwrap();
+        let count = open.entry(tenant_id).or_insert(0);
+        if *count >= self.config.max_connections_per_tenant {
+            return None;
+        }
+        *count += 1;
+        Some(StreamSlot {
+            tenant_id,
+            open: self.open.clone(),
+        })
+    }
+
+    /// Streams the tenant has open
+    pub fn open_streams(&self, tenant_id: Uuid) -> usize {
+        self.open.lock().unwrap().get(&tenant_id).copied().unwrap_or(0)
+    }
+
+    /// A connection subscribed to the tenant's alert channel; pub/sub needs a connection of its own
+    pub async fn subscribe(&self, tenant_id: Uuid) -> redis::RedisResult<PubSub> {
+        let mut pubsub = self.client.get_async_pubsub().await?;
+        pubsub.subscribe(alert_channel(tenant_id)).await?;
+        Ok(pubsub)
+    }
+}
+
+/// An open stream of a tenant; dropping it frees the slot
+pub struct StreamSlot {
+    tenant_id: Uuid,
+    open: Arc<Mutex<HashMap<Uuid, usize>>>,
+}
+
+impl Drop for StreamSlot {
+    fn drop(&mut self) {
+        let mut open = self.open.lock().unwrap();
+        if let Some(count) = open.get_mut(&self.tenant_id) {
+            *count -= 1;
+            if *count == 0 {
+                open.remove(&self.tenant_id);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use erp_master_data::inventory::AlertType;
+    use erp_master_data::inventory::model::AlertStatus;
+
+    fn alert(severity: AlertSeverity) -> InventoryAlert {
+        InventoryAlert {
+            id: Uuid::new_v4(),
+            product_id: Uuid::new_v4(),
+            location_id: Uuid::new_v4(),
+            alert_type: AlertType::LowStock,
+            severity,
+            title: "Low stock".to_string(),
+            description: None,
+            current_quantity: 3,
+            threshold_value: rust_decimal::Decimal::new(10, 0),
+            recommended_action: None,
+            alert_status: AlertStatus::Active,
+            created_at: chrono::Utc::now(),
+            acknowledged_at: None,
+            acknowledged_by: None,
+            resolved_at: None,
+            resolved_by: None,
+            resolution_notes: None,
+        }
+    }
+
+    fn streams(max_connections_per_tenant: usize) -> InventoryAlertStreams {
+        InventoryAlertStreams::new(
+            redis::Client::open("redis://localhost:6379").unwrap(),
+            InventoryAlertsConfig { max_connections_per_tenant },
+        )
+    }
+
+    #[test]
+    fn test_severity_filter_forwards_only_listed_severities() {
+        let filter = SeverityFilter::parse(Some("High, CRITICAL")).unwrap();
+        assert!(filter.matches(&alert(AlertSeverity::High)));
+        assert!(filter.matches(&alert(AlertSeverity::Critical)));
+        assert!(!filter.matches(&alert(AlertSeverity::Low)));
+
+        let everything = SeverityFilter::parse(None).unwrap();
+        assert!(everything.matches(&alert(AlertSeverity::Info)));
+        assert!(SeverityFilter::parse(Some("high,severe")).is_none());
+    }
+
+    #[test]
+    fn test_streams_are_limited_per_tenant_and_freed_on_drop() {
+        let streams = streams(2);
+        let tenant = Uuid::new_v4();
+        let other = Uuid::new_v4();
+
+        let first = streams.claim(tenant).unwrap();
+        let _second = streams.claim(tenant).unwrap();
+        assert!(streams.claim(tenant).is_none());
+        assert!(streams.claim(other).is_some(), "the limit is per tenant");
+
+        drop(first);
+        assert_eq!(streams.open_streams(tenant), 1);
+        assert!(streams.claim(tenant).is_some());
+    }
+
+    #[test]
+    fn test_channels_are_per_tenant() {
+        let tenant = Uuid::new_v4();
+        assert_eq!(alert_channel(tenant), format!("inventory_alerts:{}", tenant));
+        assert_ne!(alert_channel(tenant), alert_channel(Uuid::new_v4()));
+    }
+}
@@ -0,0 +1,80 @@
+//! # User Invitation Sweep
+//!
+//! Once a day, for every active tenant, sends the reminder for invitations
+//! halfway to their expiry and deletes the invited users whose invitation
+//! expired unaccepted, so they stop holding a seat. An administrator can
+//! reactivate such a user to invite them again.
+
+use chrono::Utc;
+use erp_auth::AuthService;
+use erp_core::{InvitationConfig, TenantContext, TenantId};
+use sqlx::{PgPool, Row};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use crate::retention::next_run;
+
+/// Daily invitation reminders and expiry
+pub struct InvitationSweepService {
+    pool: PgPool,
+    auth_service: Arc<AuthService>,
+    config: InvitationConfig,
+}
+
+impl InvitationSweepService {
+    pub fn new(pool: PgPool, auth_service: Arc<AuthService>, config: InvitationConfig) -> Self {
+        Self {
+            pool,
+            auth_service,
+            config,
+        }
+    }
+
+    /// Sweep the invitations of every active tenant; returns the reminders
+    /// sent and the invited users deleted
+    pub async fn sweep_all(&self) -> Result<(u32, usize), String> {
+        let tenants = sqlx::query("SELECT id, schema_name FROM public.tenants WHERE status = 'active' ORDER BY id")
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let (mut reminded, mut expired) = (0, 0);
+        for row in tenants {
+            let tenant = TenantContext {
+                tenant_id: TenantId(row.get("id")),
+                schema_name: row.get("schema_name"),
+            };
+            match self.auth_service.sweep_invitations(&tenant).await {
+                Ok(sweep) => {
+                    reminded += sweep.reminded;
+                    expired += sweep.expired_users.len();
+                }
+                Err(e) => warn!("Invitation sweep of tenant {} failed: {}", tenant.tenant_id.0, e),
+            }
+        }
+
+        Ok((reminded, expired))
+    }
+
+    /// Sweep daily at `invitations.sweep_at_hour_utc` in the background
+    pub fn spawn(self: &Arc<Self>) -> tokio::task::JoinHandle<()> {
+        let service = Arc::clone(self);
+        tokio::spawn(async move {
+            loop {
+                let now = Utc::now();
+                let wait = (next_run(now, service.config.sweep_at_hour_utc) - now)
+                    .to_std()
+                    .unwrap_or_default();
+                tokio::time::sleep(wait).await;
+
+                match service.sweep_all().await {
+                    Ok((0, 0)) => {}
+                    Ok((reminded, expired)) => {
+                        info!("Sent {} invitation reminders, deleted {} users with expired invitations", reminded, expired)
+                    }
+                    Err(e) => warn!("Invitation sweep failed: {}", e),
+                }
+            }
+        })
+    }
+}
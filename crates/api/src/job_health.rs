@@ -0,0 +1,220 @@
+//! # Job Queue Health Monitoring
+//!
+//! Samples the Redis job queues listed in `job_health.queues` every
+//! `job_health.sample_interval_seconds` and evaluates them with
+//! [`QueueHealthEvaluator`]: a queue nobody polls, or whose backlog keeps
+//! growing, is reported unhealthy. Results back the soft `jobs` check of
+//! `/ready` and the per-queue gauges; a queue turning unhealthy is logged as a
+//! warning, its recovery as info.
+
+use chrono::Utc;
+use erp_core::jobs::{JobQueue, QueueHealth, QueueHealthEvaluator};
+use erp_core::{JobHealthConfig, MetricsRegistry};
+use prometheus::{IntGaugeVec, Opts};
+use std::{collections::HashMap, sync::Arc, time::Duration};
+use tokio::sync::{Mutex, RwLock};
+use tracing::{info, warn};
+
+/// Evaluates job queues on a schedule and keeps the latest results
+pub struct JobHealthMonitor {
+    queues: Vec<(String, Arc<dyn JobQueue>)>,
+    config: JobHealthConfig,
+    evaluator: Mutex<QueueHealthEvaluator>,
+    latest: RwLock<Vec<QueueHealth>>,
+    depth: IntGaugeVec,
+    oldest_job_age: IntGaugeVec,
+    heartbeat_age: IntGaugeVec,
+    unhealthy: IntGaugeVec,
+}
+
+impl JobHealthMonitor {
+    pub fn new(
+        queues: Vec<(String, Arc<dyn JobQueue>)>,
+        config: JobHealthConfig,
+        namespace: &str,
+    ) -> Result<Self, prometheus::Error> {
+        let gauge = |name: &str, help: &str| {
+            IntGaugeVec::new(Opts::new(format!("{}_job_queue_{}", namespace, name), help), &["queue"])
+        };
+
+        Ok(Self {
+            queues,
+            evaluator: Mutex::new(QueueHealthEvaluator::new(config.clone())),
+            config,
+            latest: RwLock::new(Vec::new()),
+            depth: gauge("depth", "Jobs ready to run")?,
+            oldest_job_age: gauge("oldest_job_age_seconds", "Age of the oldest job ready to run")?,
+            heartbeat_age: gauge("heartbeat_age_seconds", "Seconds since a consumer last polled the queue")?,
+            unhealthy: gauge("unhealthy", "1 when the queue has no live consumer or a growing backlog")?,
+        })
+    }
+
+    pub fn register(&self, metrics: &MetricsRegistry) -> Result<(), prometheus::Error> {
+        metrics.register(self.depth.clone())?;
+        metrics.register(self.oldest_job_age.clone())?;
+        metrics.register(self.heartbeat_age.clone())?;
+        metrics.register(self.unhealthy.clone())?;
+        Ok(())
+    }
+
+    /// Results of the last check; empty before the first one
+    pub async fn latest(&self) -> Vec<QueueHealth> {
+        self.latest.read().await.clone()
+    }
+
+    /// Sample every queue once
+    pub async fn check(&self) -> Vec<QueueHealth> {
+        let mut reports = Vec::with_capacity(self.queues.len());
+        for (name, queue) in &self.queues {
+            let sample = async { Ok::<_, erp_core::Error>((queue.depth().await?, queue.heartbeats().await?)) };
+            let report = match sample.await {
+                Ok((depth, heartbeats)) => self.evaluator.lock().await.evaluate(name, Utc::now(), &depth, &heartbeats),
+                Err(e) => QueueHealth::unavailable(name, Utc::now(), e.to_string()),
+            };
+            self.record_metrics(&report);
+            reports.push(report);
+        }
+
+        let mut latest = self.latest.write().await;
+        let was_healthy: HashMap<&str, bool> = latest.iter().map(|r| (r.queue.as_str(), r.healthy)).collect();
+        for report in &reports {
+            match (was_healthy.get(report.queue.as_str()).copied().unwrap_or(true), report.healthy) {
+                (true, false) => warn!("Job queue {} is unhealthy: {:?}", report.queue, report.issues),
+                (false, true) => info!("Job queue {} recovered", report.queue),
+                _ => {}
+            }
+        }
+        *latest = reports.clone();
+        reports
+    }
+
+    fn record_metrics(&self, report: &QueueHealth) {
+        let labels = [report.queue.as_str()];
+        self.depth.with_label_values(&labels).set(report.depth as i64);
+        self.oldest_job_age
+            .with_label_values(&labels)
+            .set(report.oldest_job_age_seconds.unwrap_or(0));
+        // No heartbeat at all reads as -1
+        self.heartbeat_age
+            .with_label_values(&labels)
+            .set(report.heartbeat_age_seconds.unwrap_or(-1));
+        self.unhealthy.with_label_values(&labels).set(i64::from(!report.healthy));
+    }
+
+    /// Check the queues every `sample_interval_seconds`
+    pub fn spawn(self: &Arc<Self>) {
+        if !self.config.enabled || self.queues.is_empty() {
+            info!("Job queue health monitoring disabled");
+            return;
+        }
+
+        let monitor = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(monitor.config.sample_interval_seconds.max(1)));
+            loop {
+                interval.tick().await;
+                monitor.check().await;
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use chrono::DateTime;
+    use erp_core::jobs::traits::QueueStats;
+    use erp_core::jobs::{JobId, JobState, JobStatus, QueueDepth, QueueHeartbeat, QueueIssue};
+    use erp_core::jobs::types::QueuedJob;
+    use erp_core::Result;
+
+    /// Queue with a fixed depth whose consumer stopped polling at `last_poll`
+    struct FakeQueue {
+        queued: u64,
+        last_poll: Option<DateTime<Utc>>,
+    }
+
+    #[async_trait]
+    impl JobQueue for FakeQueue {
+        async fn enqueue(&self, job: QueuedJob) -> Result<JobId> {
+            Ok(job.id)
+        }
+
+        async fn dequeue(&self, _worker_id: &str) -> Result<Option<QueuedJob>> {
+            Ok(None)
+        }
+
+        async fn get_status(&self, _job_id: &JobId) -> Result<Option<JobStatus>> {
+            Ok(None)
+        }
+
+        async fn update_status(&self, _job_id: &JobId, _status: JobStatus) -> Result<()> {
+            Ok(())
+        }
+
+        async fn cancel_job(&self, _job_id: &JobId) -> Result<bool> {
+            Ok(false)
+        }
+
+        async fn get_stats(&self) -> Result<QueueStats> {
+            Ok(QueueStats::default())
+        }
+
+        async fn cleanup_old_jobs(&self, _older_than: DateTime<Utc>) -> Result<u64> {
+            Ok(0)
+        }
+
+        async fn get_jobs_by_status(&self, _status: JobState, _limit: Option<u32>) -> Result<Vec<QueuedJob>> {
+            Ok(Vec::new())
+        }
+
+        async fn health_check(&self) -> Result<bool> {
+            Ok(true)
+        }
+
+        async fn heartbeats(&self) -> Result<Vec<QueueHeartbeat>> {
+            Ok(self
+                .last_poll
+                .map(|last_poll| QueueHeartbeat {
+                    instance_id: "worker-1".to_string(),
+                    last_poll,
+                    in_flight: 1,
+                })
+                .into_iter()
+                .collect())
+        }
+
+        async fn depth(&self) -> Result<QueueDepth> {
+            Ok(QueueDepth {
+                queued: self.queued,
+                oldest_enqueued_at: Some(Utc::now() - chrono::Duration::minutes(3)),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stopped_consumer_is_reported_and_measured() {
+        let queues: Vec<(String, Arc<dyn JobQueue>)> = vec![
+            ("emails".to_string(), Arc::new(FakeQueue { queued: 4, last_poll: Some(Utc::now()) })),
+            (
+                "alerts".to_string(),
+                Arc::new(FakeQueue { queued: 12, last_poll: Some(Utc::now() - chrono::Duration::minutes(5)) }),
+            ),
+        ];
+        let monitor = JobHealthMonitor::new(queues, JobHealthConfig::default(), "test").unwrap();
+        assert!(monitor.latest().await.is_empty());
+
+        let reports = monitor.check().await;
+        assert!(reports[0].healthy);
+        assert!(!reports[1].healthy);
+        assert!(matches!(reports[1].issues[..], [QueueIssue::StaleHeartbeat { .. }]));
+        assert_eq!(monitor.latest().await.len(), 2);
+
+        assert_eq!(monitor.unhealthy.with_label_values(&["emails"]).get(), 0);
+        assert_eq!(monitor.unhealthy.with_label_values(&["alerts"]).get(), 1);
+        assert_eq!(monitor.depth.with_label_values(&["alerts"]).get(), 12);
+        assert!(monitor.oldest_job_age.with_label_values(&["alerts"]).get() >= 180);
+        assert!(monitor.heartbeat_age.with_label_values(&["alerts"]).get() >= 300);
+    }
+}
@@ -0,0 +1,175 @@
+//! # Export Letterheads
+//!
+//! Storage of the tenant legal entity details printed at the top of exports
+//! (see [`erp_core::export_header`]):
+//!
+//! - `public.tenant_letterheads` holds one letterhead per tenant. Tenants
+//!   without a row export without entity lines.
+//! - `public.tenant_letterhead_changes` records every save with the previous
+//!   and the new values and the user who made it.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use erp_core::export_header::{ExportHeader, TenantLetterhead};
+use erp_core::{Address, Error, ErrorCode, RequestContext, Result};
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::types::Json;
+use sqlx::{PgPool, Row};
+use uuid::Uuid;
+
+/// Most changes listed at once
+pub const MAX_CHANGES: i64 = 200;
+
+/// One saved change of a tenant's letterhead
+#[derive(Debug, Clone, Serialize)]
+pub struct LetterheadChange {
+    pub id: i64,
+    /// `None` for the first letterhead of the tenant
+    pub previous: Option<TenantLetterhead>,
+    pub current: TenantLetterhead,
+    pub changed_by: Option<Uuid>,
+    pub changed_at: DateTime<Utc>,
+}
+
+#[async_trait]
+pub trait LetterheadStore: Send + Sync {
+    /// `None` when the tenant has no letterhead
+    async fn letterhead(&self, tenant_id: Uuid) -> Result<Option<TenantLetterhead>>;
+
+    /// Replace the letterhead, recording the change; expects a normalized letterhead
+    async fn save(&self, tenant_id: Uuid, letterhead: &TenantLetterhead, changed_by: Option<Uuid>) -> Result<LetterheadChange>;
+
+    /// Most recent changes first
+    async fn changes(&self, tenant_id: Uuid, limit: i64) -> Result<Vec<LetterheadChange>>;
+}
+
+/// Header of an export requested by the caller for their tenant, with the
+/// tenant's letterhead if it has one
+pub async fn export_header(
+    store: &dyn LetterheadStore,
+    tenant_id: Uuid,
+    title: &str,
+    request_context: &RequestContext,
+) -> Result<ExportHeader> {
+    let letterhead = store.letterhead(tenant_id).await?;
+    let requested_by = match (request_context.user_id, &request_context.impersonator_id) {
+        (Some(user_id), Some(impersonator)) => format!("{} (impersonated by {})", user_id, impersonator.0),
+        (Some(user_id), None) => user_id.to_string(),
+        (None, _) => "unknown".to_string(),
+    };
+    Ok(ExportHeader::new(title, letterhead, requested_by))
+}
+
+pub struct PostgresLetterheadStore {
+    pool: PgPool,
+}
+
+impl PostgresLetterheadStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+fn letterhead_from_json(value: Value) -> Result<TenantLetterhead> {
+    serde_json::from_value(value)
+        .map_err(|e| Error::new(ErrorCode::DatabaseError, format!("Unreadable letterhead change: {}", e)))
+}
+
+fn letterhead_from_row(row: &sqlx::postgres::PgRow) -> TenantLetterhead {
+    TenantLetterhead {
+        legal_name: row.get("legal_name"),
+        registration_number: row.get("registration_number"),
+        vat_id: row.get("vat_id"),
+        registered_address: row.get::<Option<Address>, _>("registered_address"),
+        logo_ref: row.get("logo_ref"),
+    }
+}
+
+fn change_from_row(row: &sqlx::postgres::PgRow) -> Result<LetterheadChange> {
+    Ok(LetterheadChange {
+        id: row.get("id"),
+        previous: row.get::<Option<Value>, _>("previous").map(letterhead_from_json).transpose()?,
+        current: letterhead_from_json(row.get("current"))?,
+        changed_by: row.get("changed_by"),
+        changed_at: row.get("changed_at"),
+    })
+}
+
+const LETTERHEAD_COLUMNS: &str = "legal_name, registration_number, vat_id, registered_address, logo_ref";
+
+#[async_trait]
+impl LetterheadStore for PostgresLetterheadStore {
+    async fn letterhead(&self, tenant_id: Uuid) -> Result<Option<TenantLetterhead>> {
+        let row = sqlx::query(&format!(
+            "SELECT {} FROM public.tenant_letterheads WHERE tenant_id = $1",
+            LETTERHEAD_COLUMNS
+        ))
+        .bind(tenant_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.as_ref().map(letterhead_from_row))
+    }
+
+    async fn save(&self, tenant_id: Uuid, letterhead: &TenantLetterhead, changed_by: Option<Uuid>) -> Result<LetterheadChange> {
+        let mut tx = self.pool.begin().await?;
+        // Lock the current row so concurrent saves record a consistent history
+        let previous = sqlx::query(&format!(
+            "SELECT {} FROM public.tenant_letterheads WHERE tenant_id = $1 FOR UPDATE",
+            LETTERHEAD_COLUMNS
+        ))
+        .bind(tenant_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .as_ref()
+        .map(letterhead_from_row);
+
+        let now = Utc::now();
+        sqlx::query(
+            "INSERT INTO public.tenant_letterheads \
+                 (tenant_id, legal_name, registration_number, vat_id, registered_address, logo_ref, updated_by, updated_at) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8) \
+             ON CONFLICT (tenant_id) DO UPDATE SET \
+                 legal_name = EXCLUDED.legal_name, registration_number = EXCLUDED.registration_number, \
+                 vat_id = EXCLUDED.vat_id, registered_address = EXCLUDED.registered_address, \
+                 logo_ref = EXCLUDED.logo_ref, updated_by = EXCLUDED.updated_by, updated_at = EXCLUDED.updated_at",
+        )
+        .bind(tenant_id)
+        .bind(&letterhead.legal_name)
+        .bind(&letterhead.registration_number)
+        .bind(&letterhead.vat_id)
+        .bind(&letterhead.registered_address)
+        .bind(&letterhead.logo_ref)
+        .bind(changed_by)
+        .bind(now)
+        .execute(&mut *tx)
+        .await?;
+
+        let row = sqlx::query(
+            "INSERT INTO public.tenant_letterhead_changes (tenant_id, previous, current, changed_by, changed_at) \
+             VALUES ($1, $2, $3, $4, $5) \
+             RETURNING id, previous, current, changed_by, changed_at",
+        )
+        .bind(tenant_id)
+        .bind(previous.as_ref().map(Json))
+        .bind(Json(letterhead))
+        .bind(changed_by)
+        .bind(now)
+        .fetch_one(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        change_from_row(&row)
+    }
+
+    async fn changes(&self, tenant_id: Uuid, limit: i64) -> Result<Vec<LetterheadChange>> {
+        let rows = sqlx::query(
+            "SELECT id, previous, current, changed_by, changed_at FROM public.tenant_letterhead_changes \
+             WHERE tenant_id = $1 ORDER BY changed_at DESC, id DESC LIMIT $2",
+        )
+        .bind(tenant_id)
+        .bind(limit.clamp(1, MAX_CHANGES))
+        .fetch_all(&self.pool)
+        .await?;
+        rows.iter().map(change_from_row).collect()
+    }
+}
@@ -0,0 +1,1185 @@
+//! # ERP System API
+//!
+//! The application behind the `erp-server` binary. [`build_state`] wires the
+//! services onto the database and Redis connections, [`create_app`] builds
+//! the router with its middleware on top of them. The server and the
+//! integration tests boot the same application this way.
+
+mod activity;
+mod admin_lite;
+mod api_usage;
+mod build_info;
+mod business_calendar;
+mod credit_standing;
+mod customer_encryption;
+mod error;
+mod exchange_rates;
+mod existence_checks;
+mod error_handler;
+mod follow_up_reminders;
+mod global_search;
+mod handlers;
+mod health;
+mod idempotency;
+mod inventory_alerts;
+mod invitations;
+mod job_health;
+mod letterhead;
+mod month_end_snapshots;
+mod api_middleware;
+mod backpressure;
+mod notification_digest;
+pub mod openapi;
+mod responses;
+mod state;
+mod permission_usage;
+mod plan_limits;
+mod product_lifecycle;
+mod quality_inspections;
+mod reservation_reconciliation;
+mod retention;
+mod sandbox;
+mod sync;
+mod tenant_health;
+mod transfer_approvals;
+mod webhooks;
+
+use crate::{
+    activity::{ActivityRetention, ActivityService, ActivityStore, PostgresActivityStore},
+    api_usage::{api_usage_middleware, ApiUsageService, RedisRateLimitCounter},
+    api_middleware::api_version::{api_version_middleware, ApiVersionPolicy},
+    api_middleware::rate_limit::{RateLimiter, RedisSlidingWindow},
+    business_calendar::{CalendarStore, PostgresCalendarStore},
+    credit_standing::{CreditStandingService, PostgresCreditStandingStore},
+    letterhead::{LetterheadStore, PostgresLetterheadStore},
+    customer_encryption::CustomerEncryptionJob,
+    follow_up_reminders::{CommunicationEmailLogger, FollowUpReminderService, PostgresReminderDirectory},
+    invitations::InvitationSweepService,
+    handlers::{activity as activity_handlers, audit as audit_handlers, admin, admin_lite as admin_lite_handlers, api_usage as api_usage_handlers, async_results, atp, auth, calendar, capacity, consignment, credit_standing as credit_handlers, currency, customer_csv, email_templates as email_template_handlers, existence as existence_handlers, inventory_costing, search as search_handlers, inventory_snapshots, letterhead as letterhead_handlers, meta, sessions as session_handlers, users, roles, customers, communications, inventory, inventory_alerts as inventory_alert_handlers, notifications, picking, plan_limits as plan_limit_handlers, portal_users, products, reports, returns, suppliers, sync as sync_handlers, territories, transfers, webhooks as webhook_handlers},
+    job_health::JobHealthMonitor,
+    month_end_snapshots::MonthEndSnapshotService,
+    notification_digest::{NotificationService, PostgresNotificationStore},
+    permission_usage::PermissionUsageService,
+    plan_limits::PlanLimitService,
+    product_lifecycle::ProductLifecycleJob,
+    quality_inspections::QualityInspectionJob,
+    reservation_reconciliation::ReservationReconciliationService,
+    retention::{PostgresRetentionStore, RetentionService},
+    backpressure::{backpressure_middleware, Backpressure, PostgresAsyncResultStore, EXPORTS_GROUP, REPORTS_GROUP},
+    exchange_rates::{EcbRateFetcher, ECB_RATES_INTEGRATION},
+    existence_checks::{existence_rate_limit_middleware, ExistenceChecks, PostgresExistenceLookup, EXISTENCE_RATE_LIMIT_PREFIX},
+    global_search::GlobalSearch,
+    idempotency::{Idempotency, PostgresIdempotencyStore},
+    inventory_alerts::InventoryAlertStreams,
+    sandbox::{PostgresSandboxStore, SandboxResetService, WebhookAnnouncer, SANDBOX_WEBHOOK_INTEGRATION},
+    sync::{ChangeLogRetention, ChangeLogStore, PostgresChangeLog, SyncService},
+    tenant_health::{PostgresTenantProbe, TenantHealthMonitor},
+    transfer_approvals::{PostgresPermissionHolders, TransferApprovalNotifier},
+    webhooks::{
+        sources::{AuthEventSource, CustomerEventSource, InventoryEventSource},
+        EventSource, OutboundTransport, PostgresWebhookStore, WebhookDeliveryRetention, WebhookService, WebhookStore,
+        WEBHOOK_INTEGRATION,
+    },
+};
+use axum::{
+    Router,
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use erp_auth::email::EmailJobHandler;
+use erp_auth::service::AUTH_JOB_QUEUE;
+use erp_auth::{AuthService, EmailService};
+use erp_core::api_usage::ApiUsageStore;
+use erp_core::plan_limits::{PlanLimitEnforcer, PlanLimitGuard, PlanUsageStore, PostgresPlanUsageStore};
+use erp_core::audit::{AuditBackend, DatabaseAuditRepository};
+use erp_core::numbering::BlockAllocator;
+use erp_core::jobs::ExecutorConfig;
+use erp_core::retention::RetentionRegistry;
+use erp_core::{outbound::OutboundClientFactory, Config, CorsConfig, DatabasePool, HttpMetrics, JobExecutor, JobQueue, LatencyBudget, LatencyMetrics, MetricsRegistry, NumberingMetrics, OutboundMetrics, RedisJobQueue};
+use erp_master_data::customer::{
+    CommunicationRepository, CommunicationRetention, DeletedCustomerPurge, PostgresCommunicationRepository,
+};
+use erp_master_data::currency::PostgresExchangeRateRepository;
+use erp_master_data::product::PostgresProductRepository;
+use erp_master_data::inventory::{
+    PostgresInventorySnapshotRepository, PostgresReservationReconciliationRepository, PostgresStockTransferRepository,
+};
+use redis::aio::ConnectionManager;
+use std::sync::Arc;
+use tower::ServiceBuilder;
+use tower_http::{
+    compression::CompressionLayer,
+    cors::{CorsLayer, Any},
+    trace::{DefaultMakeSpan, DefaultOnRequest, DefaultOnResponse, TraceLayer},
+};
+use axum::http::{Method, HeaderName, HeaderValue};
+use tracing::{error, info, Level};
+use utoipa_swagger_ui::SwaggerUi;
+
+pub use build_info::BuildTaggedFormat;
+pub use state::AppState;
+
+/// Builds a CORS layer from configuration settings.
+/// 
+/// This function creates a tower-http CORS layer based on the application's
+/// CORS configuration. It supports both permissive development settings
+/// and restrictive production policies.
+/// 
+/// # Configuration Options
+/// 
+/// - **Origins**: Specific domains or "*" wildcard (development only)
+/// - **Methods**: HTTP methods allowed for cross-origin requests
+/// - **Headers**: Request headers permitted in CORS requests
+/// - **Credentials**: Whether to allow cookies and authorization headers
+/// - **Max Age**: How long browsers cache preflight responses
+/// 
+/// # Security Notes
+/// 
+/// - Production should never use "*" for allowed origins
+/// - Credentials should only be enabled with specific origins
+/// - Headers should be limited to necessary values only
+/// 
+/// # Examples
+/// 
+/// ```rust
+/// let cors_config = CorsConfig {
+///     allowed_origins: vec!["https://myapp.com".to_string()],
+///     allowed_methods: vec!["GET".to_string(), "POST".to_string()],
+///     allowed_headers: vec!["authorization".to_string()],
+///     expose_headers: vec!["x-request-id".to_string()],
+///     allow_credentials: true,
+///     max_age: Some(3600),
+/// };
+/// 
+/// let cors_layer = build_cors_layer(&cors_config)?;
+/// ```
+fn build_cors_layer(cors_config: &CorsConfig) -> Result<CorsLayer, Box<dyn std::error::Error>> {
+    let mut cors = CorsLayer::new();
+    
+    // Configure allowed origins
+    if cors_config.allowed_origins.contains(&"*".to_string()) {
+        cors = cors.allow_origin(Any);
+    } else {
+        let origins: Result<Vec<HeaderValue>, _> = cors_config.allowed_origins
+            .iter()
+            .map(|origin| origin.parse())
+            .collect();
+        cors = cors.allow_origin(origins?);
+    }
+    
+    // Configure allowed methods
+    if cors_config.allowed_methods.contains(&"*".to_string()) {
+        cors = cors.allow_methods(Any);
+    } else {
+        let methods: Result<Vec<Method>, _> = cors_config.allowed_methods
+            .iter()
+            .map(|method| method.parse())
+            .collect();
+        cors = cors.allow_methods(methods?);
+    }
+    
+    // Configure allowed headers
+    if cors_config.allowed_headers.contains(&"*".to_string()) {
+        cors = cors.allow_headers(Any);
+    } else {
+        let headers: Result<Vec<HeaderName>, _> = cors_config.allowed_headers
+            .iter()
+            .map(|header| header.parse())
+            .collect();
+        cors = cors.allow_headers(headers?);
+    }
+    
+    // Configure exposed headers
+    if !cors_config.expose_headers.is_empty() {
+        let expose_headers: Result<Vec<HeaderName>, _> = cors_config.expose_headers
+            .iter()
+            .map(|header| header.parse())
+            .collect();
+        cors = cors.expose_headers(expose_headers?);
+    }
+    
+    // Configure credentials
+    cors = cors.allow_credentials(cors_config.allow_credentials);
+    
+    // Configure max age
+    if let Some(max_age) = cors_config.max_age {
+        cors = cors.max_age(std::time::Duration::from_secs(max_age));
+    }
+    
+    Ok(cors)
+}
+
+
+/// Background jobs of the application, started once the server is about to
+/// serve requests
+///
+/// The integration tests never start them, so no nightly job or digest runs
+/// in between their requests.
+#[derive(Default)]
+pub struct BackgroundJobs {
+    jobs: Vec<Box<dyn FnOnce() + Send>>,
+}
+
+impl BackgroundJobs {
+    fn add(&mut self, job: impl FnOnce() + Send + 'static) {
+        self.jobs.push(Box::new(job));
+    }
+
+    /// Start every job; needs a running Tokio runtime
+    pub fn spawn(self) {
+        for job in self.jobs {
+            job();
+        }
+    }
+}
+
+/// Wire the services of the application onto its database and Redis
+/// connections
+///
+/// Migrations must have run already. The background jobs are returned
+/// unstarted.
+pub async fn build_state(
+    config: Config,
+    db: DatabasePool,
+    redis: ConnectionManager,
+) -> Result<(AppState, BackgroundJobs), Box<dyn std::error::Error>> {
+    let mut jobs = BackgroundJobs::default();
+
+    let auth_service = Arc::new(
+        AuthService::new(db.clone(), redis.clone(), config.clone()).await?
+    );
+    info!("Auth service initialized");
+
+    // Metrics and tenant health monitoring
+    let metrics = MetricsRegistry::new(config.metrics.clone());
+    let tenant_health = Arc::new(TenantHealthMonitor::new(
+        Arc::new(PostgresTenantProbe::new(
+            db.main_pool.clone(),
+            config.tenant_health.sentinel_table.clone(),
+        )),
+        config.tenant_health.clone(),
+        erp_core::tenant_template::tenant_template_version(),
+        &config.metrics.namespace,
+    )?);
+    metrics.register(tenant_health.unhealthy_counter())?;
+
+    // Job queues: a queue nobody consumes or whose backlog keeps growing shows up in /ready and metrics
+    let job_queues: Vec<(String, Arc<dyn JobQueue>)> = config
+        .job_health
+        .queues
+        .iter()
+        .map(|name| (name.clone(), Arc::new(RedisJobQueue::new(redis.clone(), name.clone())) as Arc<dyn JobQueue>))
+        .collect();
+    let job_health = Arc::new(JobHealthMonitor::new(job_queues, config.job_health.clone(), &config.metrics.namespace)?);
+    job_health.register(&metrics)?;
+    let job = job_health.clone();
+    jobs.add(move || { job.spawn(); });
+
+    // Differential sync for offline clients
+    let change_log: Arc<dyn ChangeLogStore> = Arc::new(PostgresChangeLog::new(db.clone()));
+    let sync = Arc::new(SyncService::new(change_log.clone(), config.sync.clone()));
+
+    // Response schema versioning
+    let api_versions = Arc::new(ApiVersionPolicy::new(&config.api_versioning, &config.metrics.namespace)?);
+    metrics.register(api_versions.usage_counter())?;
+
+    // Backpressure: expensive endpoints limited per group, shed or answered asynchronously when busy
+    let backpressure = Arc::new(Backpressure::new(
+        config.backpressure.clone(),
+        Arc::new(PostgresAsyncResultStore::new(db.main_pool.clone())),
+        &config.metrics.namespace,
+    )?);
+    backpressure.register(&metrics)?;
+    let job = backpressure.clone();
+    jobs.add(move || { job.spawn(); });
+
+    // Idempotency keys: retried create requests get the first response instead of a second entity
+    let idempotency = Arc::new(Idempotency::new(
+        config.idempotency.clone(),
+        Arc::new(PostgresIdempotencyStore::new(db.main_pool.clone())),
+    ));
+    let job = idempotency.clone();
+    jobs.add(move || { job.spawn(); });
+
+    // Rate limits: sliding windows in Redis per caller and route class, stricter for the auth endpoints
+    let rate_limiter = match config.rate_limit.enabled {
+        true => {
+            let limiter = RateLimiter::new(
+                Arc::new(RedisSlidingWindow::new(redis.clone())),
+                auth_service.jwt_service(),
+                &config.rate_limit,
+                &config.metrics.namespace,
+            )?;
+            limiter.register(&metrics)?;
+            Some(Arc::new(limiter))
+        }
+        false => None,
+    };
+
+    // Global search: type-ahead across entity types, each searched only with its read permission
+    let global_search = Arc::new(GlobalSearch::new(config.global_search.clone()));
+
+    // Inventory alert streams: each subscribes to its tenant's alert channel on a pub/sub connection of its own
+    let inventory_alerts = Arc::new(InventoryAlertStreams::new(
+        redis::Client::open(config.redis.url.as_str())?,
+        config.inventory_alerts.clone(),
+    ));
+
+    // Outbound HTTP to partner systems: shared timeouts, circuit breakers and egress rules
+    let outbound_metrics = OutboundMetrics::new(&config.metrics.namespace)?;
+    metrics.register(outbound_metrics.requests_total.clone())?;
+    metrics.register(outbound_metrics.request_duration_seconds.clone())?;
+    metrics.register(outbound_metrics.breaker_state.clone())?;
+    let outbound = Arc::new(OutboundClientFactory::new(config.outbound.clone()).with_metrics(outbound_metrics));
+
+    // Customer communication log: emails to customers are logged, follow-ups reminded daily
+    let communications: Arc<dyn CommunicationRepository> =
+        Arc::new(PostgresCommunicationRepository::new(db.main_pool.clone()));
+    let mut email_service = EmailService::new(config.email.clone())?
+        .with_observer(Arc::new(CommunicationEmailLogger::new(communications.clone())));
+    if let Some(audit_logger) = auth_service.audit_logger() {
+        // Where the `log` provider records messages instead of sending them
+        email_service = email_service.with_audit_logger(audit_logger);
+    }
+    let email = Arc::new(email_service);
+
+    // Queued auth jobs, such as emails, sent with the shared email service and its pooled connections
+    let mut auth_jobs = JobExecutor::new(
+        Arc::new(RedisJobQueue::new(redis.clone(), AUTH_JOB_QUEUE)),
+        ExecutorConfig::default(),
+    );
+    auth_jobs
+        .register_handler(Arc::new(EmailJobHandler::new(email.clone(), auth_service.audit_logger())))
+        .await;
+    jobs.add(move || {
+        tokio::spawn(async move {
+            if let Err(e) = auth_jobs.start().await {
+                error!("Failed to start the auth job executor: {}", e);
+                return;
+            }
+            // Dropping the executor would stop its workers
+            std::future::pending::<()>().await;
+        });
+    });
+    let follow_up_reminders = Arc::new(FollowUpReminderService::new(
+        communications,
+        Arc::new(PostgresReminderDirectory::new(db.main_pool.clone(), auth_service.clone())),
+        email.clone(),
+        config.follow_up_reminders.clone(),
+    ));
+    let job = follow_up_reminders.clone();
+    jobs.add(move || { job.spawn(); });
+
+    // User invitations: reminders at half their lifetime, expired ones swept daily
+    let invitation_sweep = Arc::new(InvitationSweepService::new(
+        db.main_pool.clone(),
+        auth_service.clone(),
+        config.invitations.clone(),
+    ));
+    jobs.add(move || { invitation_sweep.spawn(); });
+
+    // Business calendars: tenant working days and holidays that scheduled jobs follow
+    let calendars: Arc<dyn CalendarStore> = Arc::new(PostgresCalendarStore::new(db.main_pool.clone()));
+
+    // Document numbers: sequences that may have gaps are leased in blocks per instance
+    let numbering_metrics = NumberingMetrics::new(&config.metrics.namespace)?;
+    metrics.register(numbering_metrics.block_refills_total.clone())?;
+    metrics.register(numbering_metrics.numbers_returned_total.clone())?;
+    let numbering = Arc::new(
+        BlockAllocator::new(db.main_pool.clone(), config.numbering.clone(), numbering_instance_id())
+            .with_metrics(numbering_metrics),
+    );
+
+    // Audit events: buffered by the auth service and written in batches, flushed on shutdown
+    if let Some(audit) = auth_service.audit_writer().as_ref().and_then(|writer| writer.metrics().cloned()) {
+        metrics.register(audit.queue_depth.clone())?;
+        metrics.register(audit.batch_size.clone())?;
+        metrics.register(audit.dropped_total.clone())?;
+        metrics.register(audit.spilled_total.clone())?;
+    }
+
+    // Latency budgets: master data service methods timed per method, slow ones logged in their span
+    let latency_metrics = LatencyMetrics::new(&config.metrics.namespace)?;
+    metrics.register(latency_metrics.method_duration_seconds.clone())?;
+    metrics.register(latency_metrics.budget_exceeded_total.clone())?;
+    let latency_budget = LatencyBudget::new(config.latency_budgets.clone()).with_metrics(latency_metrics);
+
+    // HTTP requests per route template, and the connections the database pools have checked out
+    let http_metrics = HttpMetrics::new(&config.metrics.namespace)?;
+    metrics.register(http_metrics.request_duration_seconds.clone())?;
+    metrics.register(http_metrics.client_errors_total.clone())?;
+    metrics.register(http_metrics.server_errors_total.clone())?;
+    metrics.register(http_metrics.db_pool_active_connections.clone())?;
+
+    // Export letterheads: legal entity details printed at the top of exports
+    let letterheads: Arc<dyn LetterheadStore> = Arc::new(PostgresLetterheadStore::new(db.main_pool.clone()));
+
+    // Customer credit standing: recomputed from the open items the accounting system imports
+    let credit_standing = Arc::new(CreditStandingService::new(
+        Arc::new(PostgresCreditStandingStore::new(db.main_pool.clone())),
+        &config.credit,
+    ));
+
+    // Notifications: critical ones emailed right away, the rest per user choice or in the daily digest
+    let notifications = Arc::new(
+        NotificationService::new(
+            Arc::new(PostgresNotificationStore::new(db.main_pool.clone(), auth_service.clone())),
+            email.clone(),
+            config.notification_digest.clone(),
+            config.app.base_url.clone(),
+        )
+        .with_calendar(calendars.clone()),
+    );
+    let job = notifications.clone();
+    jobs.add(move || { job.spawn(); });
+
+    // Transfer approvals: approvers notified through the notification pipeline, overdue approvals escalated
+    let transfer_approvals = Arc::new(TransferApprovalNotifier::new(
+        Arc::new(PostgresStockTransferRepository::new(db.main_pool.clone())),
+        Arc::new(PostgresPermissionHolders::new(db.clone())),
+        notifications.clone(),
+        config.transfer_approvals.clone(),
+    ));
+    let job = transfer_approvals.clone();
+    jobs.add(move || { job.spawn(); });
+
+    // Reserved quantities: reconciled with their reservations nightly, repeated drift alerted as critical
+    let reservation_reconciliation = Arc::new(
+        ReservationReconciliationService::new(
+            Arc::new(PostgresReservationReconciliationRepository::new(db.main_pool.clone())),
+            config.reservation_reconciliation.clone(),
+        )
+        .with_alerts(Arc::new(PostgresPermissionHolders::new(db.clone())), notifications.clone()),
+    );
+    let job = reservation_reconciliation.clone();
+    jobs.add(move || { job.spawn(); });
+
+    // Inventory snapshots: the month-end snapshots tenants scheduled, taken with their scope
+    let month_end_snapshots = Arc::new(MonthEndSnapshotService::new(
+        Arc::new(PostgresInventorySnapshotRepository::new(db.main_pool.clone())),
+        config.month_end_snapshots.clone(),
+    ));
+    jobs.add(move || { month_end_snapshots.spawn(); });
+
+    // Product lifecycle: stage transitions from sales and age, proposed or applied per tenant
+    let product_lifecycle = Arc::new(ProductLifecycleJob::new(
+        db.main_pool.clone(),
+        Arc::new(PostgresProductRepository::new(db.clone())),
+        config.product_lifecycle.clone(),
+    ));
+    jobs.add(move || { product_lifecycle.spawn(); });
+
+    // Quality inspections: incoming-goods and re-inspections scheduled daily, overdue ones reported once
+    let quality_inspections = Arc::new(QualityInspectionJob::new(
+        db.main_pool.clone(),
+        Arc::new(PostgresProductRepository::new(db.clone())),
+        Arc::new(PostgresPermissionHolders::new(db.clone())),
+        notifications.clone(),
+        config.quality_inspections.clone(),
+    ));
+    jobs.add(move || { quality_inspections.spawn(); });
+
+    // Plan limits: tenants measured nightly, thresholds notified once per crossing, enforced limits refuse creation
+    let plan_usage: Arc<dyn PlanUsageStore> = Arc::new(PostgresPlanUsageStore::new(db.clone()));
+    let plan_limit_guard: Arc<dyn PlanLimitGuard> = Arc::new(PlanLimitEnforcer::new(plan_usage.clone()));
+    let plan_limits = Arc::new(
+        PlanLimitService::new(plan_usage, config.plan_limits.clone())
+            .with_alerts(Arc::new(PostgresPermissionHolders::new(db.clone())), notifications.clone(), email),
+    );
+    let job = plan_limits.clone();
+    jobs.add(move || { job.spawn(); });
+
+    // Activity feed: audit, customer and inventory events projected per tenant
+    let activity_store: Arc<dyn ActivityStore> = Arc::new(PostgresActivityStore::new(db.main_pool.clone()));
+    let activity = Arc::new(ActivityService::new(activity_store.clone(), config.activity.clone()));
+    let job = activity.clone();
+    jobs.add(move || { job.spawn(); });
+    let audit: Arc<dyn AuditBackend> = Arc::new(DatabaseAuditRepository::new(Arc::new(db.main_pool.clone())));
+
+    // Tenant webhooks: customer, inventory and auth events, ordered per subscription and source
+    let webhook_store: Arc<dyn WebhookStore> = Arc::new(PostgresWebhookStore::new(db.main_pool.clone()));
+    let webhook_sources: Vec<Arc<dyn EventSource>> = vec![
+        Arc::new(CustomerEventSource::new(db.main_pool.clone())),
+        Arc::new(InventoryEventSource::new(db.main_pool.clone())),
+        Arc::new(AuthEventSource::new(db.main_pool.clone())),
+    ];
+    let webhooks = Arc::new(WebhookService::new(
+        webhook_store.clone(),
+        webhook_sources,
+        Arc::new(OutboundTransport::new(outbound.client(WEBHOOK_INTEGRATION)?)),
+        config.webhooks.clone(),
+    ));
+    let job = webhooks.clone();
+    jobs.add(move || { job.spawn(); });
+
+    // Data retention: each module registers its categories, one nightly job enforces them
+    let mut retention_registry = RetentionRegistry::new();
+    retention_registry
+        .register(DatabaseAuditRepository::new(Arc::new(db.main_pool.clone())).retention_category())
+        .register(ChangeLogRetention::category(change_log, &config.sync))
+        .register(CommunicationRetention::category(db.main_pool.clone()))
+        .register(DeletedCustomerPurge::category(db.main_pool.clone()))
+        .register(ActivityRetention::category(activity_store, &config.activity))
+        .register(WebhookDeliveryRetention::category(webhook_store, &config.webhooks));
+    let retention = Arc::new(RetentionService::new(
+        retention_registry,
+        Arc::new(PostgresRetentionStore::new(db.main_pool.clone())),
+        config.retention.clone(),
+    ));
+    let job = retention.clone();
+    jobs.add(move || { job.spawn(); });
+
+    // Permission usage: counted by require_permission, flushed nightly for least-privilege reviews
+    let (permission_usage, permission_uses) = PermissionUsageService::new(
+        db.clone(),
+        redis.clone(),
+        config.permission_usage.clone(),
+        &config.metrics.namespace,
+    )?;
+    let permission_usage = Arc::new(permission_usage);
+    metrics.register(permission_usage.dropped_counter())?;
+    let job = permission_usage.clone();
+    jobs.add(move || job.spawn(permission_uses));
+
+    // API usage: calls per access token summed per day for tenant admins, rate limited per token
+    let (api_usage, api_calls) = ApiUsageService::new(
+        ApiUsageStore::new(db.main_pool.clone()),
+        auth_service.jwt_service(),
+        Arc::new(RedisRateLimitCounter::new(redis.clone())),
+        config.rate_limit.requests_per_minute + config.rate_limit.burst_size,
+        config.api_usage.clone(),
+        &config.metrics.namespace,
+    )?;
+    let api_usage = Arc::new(api_usage);
+    metrics.register(api_usage.dropped_counter())?;
+    let job = api_usage.clone();
+    jobs.add(move || job.spawn(api_calls));
+
+    // Existence checks for form validation, rate limited per user apart from the token limit
+    let existence_checks = Arc::new(ExistenceChecks::new(
+        Arc::new(PostgresExistenceLookup::new(db.clone())),
+        Arc::new(RedisRateLimitCounter::with_prefix(redis.clone(), EXISTENCE_RATE_LIMIT_PREFIX)),
+        config.existence_checks.clone(),
+    ));
+
+    // Customer field encryption: tax numbers and bank accounts sealed on write, older rows backfilled
+    let customer_cipher = customer_encryption::build_cipher(&config)?;
+    if let Some(cipher) = &customer_cipher {
+        let job = Arc::new(CustomerEncryptionJob::new(db.main_pool.clone(), cipher.clone(), &config));
+        jobs.add(move || { job.spawn(); });
+    }
+
+    // Exchange rates: ECB reference rates fetched daily for pairs tenants have no rate of their own for
+    let ecb_rates = Arc::new(EcbRateFetcher::new(
+        outbound.client(ECB_RATES_INTEGRATION)?,
+        Arc::new(PostgresExchangeRateRepository::new(db.main_pool.clone())),
+        config.exchange_rates.clone(),
+    ));
+    jobs.add(move || { ecb_rates.spawn(); });
+
+    // Sandbox tenants: reset to their golden snapshot nightly, responses marked X-Sandbox
+    let mut sandbox = SandboxResetService::new(
+        Arc::new(PostgresSandboxStore::new(db.main_pool.clone())),
+        auth_service.clone(),
+        config.sandbox.clone(),
+    );
+    if let Some(url) = &config.sandbox.webhook_url {
+        sandbox = sandbox.with_announcer(Arc::new(WebhookAnnouncer::new(
+            outbound.client(SANDBOX_WEBHOOK_INTEGRATION)?,
+            url.clone(),
+        )));
+    }
+    let sandbox = Arc::new(sandbox);
+    let sandbox_tenants = sandbox.tenants();
+    jobs.add(move || { sandbox.spawn(); });
+
+    let state = AppState {
+        config,
+        db,
+        redis,
+        auth_service,
+        metrics,
+        http_metrics,
+        tenant_health,
+        job_health,
+        sync,
+        api_versions,
+        follow_up_reminders,
+        retention,
+        permission_usage,
+        api_usage,
+        existence_checks,
+        outbound,
+        activity,
+        audit,
+        webhooks,
+        notifications,
+        transfer_approvals,
+        reservation_reconciliation,
+        plan_limits,
+        plan_limit_guard,
+        customer_cipher,
+        calendars,
+        letterheads,
+        credit_standing,
+        numbering,
+        sandbox_tenants,
+        backpressure,
+        idempotency,
+        rate_limiter,
+        global_search,
+        inventory_alerts,
+        latency_budget,
+    };
+
+    Ok((state, jobs))
+}
+
+/// Build the router of the application with its middleware
+pub fn create_app(state: AppState, auth_service: Arc<AuthService>) -> Result<Router, Box<dyn std::error::Error>> {
+    // API routes, with response schema version negotiation
+    let mut api_routes = create_api_routes(&auth_service, &state.backpressure, &state.existence_checks, &state.idempotency)
+        .layer(axum::middleware::from_fn_with_state(state.api_versions.clone(), api_version_middleware));
+    // require_permission reports the permissions it grants to this recorder
+    if let Some(recorder) = state.permission_usage.recorder() {
+        api_routes = api_routes.layer(axum::Extension(recorder));
+    }
+    // Non-fatal warnings raised while handling a request go out with its response
+    let mut api_routes = api_routes.layer(axum::middleware::from_fn(api_middleware::warnings::warnings_middleware));
+    // Calls with an access token are metered and rate limited per token
+    if state.api_usage.active() {
+        api_routes = api_routes.layer(axum::middleware::from_fn_with_state(state.api_usage.clone(), api_usage_middleware));
+    }
+    // Sliding-window limits per caller and route bucket, refused before the per-token count
+    if let Some(limiter) = &state.rate_limiter {
+        api_routes = api_routes
+            .layer(axum::middleware::from_fn(api_middleware::rate_limit::rate_limit_middleware))
+            .layer(axum::Extension(limiter.clone()));
+    }
+
+    // Build the router
+    let mut router = Router::new()
+        .nest("/api/v1", api_routes);
+    if state.config.admin_lite.enabled {
+        router = router.nest("/admin-lite", create_admin_lite_routes(&auth_service, &state.config.admin_lite)?);
+    }
+    let mut router = router
+        // OpenAPI document, and the Swagger UI reading it
+        .route("/api-docs/openapi.json", axum::routing::get(openapi::openapi_json))
+        .merge(SwaggerUi::new("/swagger-ui").config(utoipa_swagger_ui::Config::from("/api-docs/openapi.json")))
+        // Health checks
+        .route("/health", axum::routing::get(health::health_check))
+        .route("/ready", axum::routing::get(health::readiness_check))
+        // Global middleware (Order matters: layers are applied from bottom to top)
+        .layer(
+            ServiceBuilder::new()
+                // Security headers (applied to all responses)
+                .layer(axum::middleware::from_fn(api_middleware::security_headers::security_headers_middleware))
+                // Request ID middleware
+                .layer(axum::middleware::from_fn(api_middleware::request_id::request_id_middleware))
+                // Tenant context extraction
+                .layer(axum::middleware::from_fn_with_state(
+                    auth_service.tenant_resolver(),
+                    api_middleware::tenant_context::tenant_context_middleware,
+                ))
+                // X-Sandbox marker for sandbox tenants
+                .layer(axum::middleware::from_fn_with_state(
+                    state.sandbox_tenants.clone(),
+                    api_middleware::sandbox::sandbox_header_middleware,
+                ))
+                // Logging and tracing
+                .layer(
+                    TraceLayer::new_for_http()
+                        .make_span_with(DefaultMakeSpan::new().level(Level::INFO))
+                        .on_request(DefaultOnRequest::new().level(Level::INFO))
+                        .on_response(DefaultOnResponse::new().level(Level::INFO)),
+                )
+                // Response compression
+                .layer(CompressionLayer::new())
+                // CORS (should be outermost)
+                .layer(build_cors_layer(&state.config.cors)?),
+        );
+
+    // Request metrics around everything above; the scrape endpoint itself is added
+    // afterwards, so it is neither timed nor behind the tenant and auth middleware
+    if state.config.metrics.enabled {
+        router = router.layer(axum::middleware::from_fn_with_state(
+            state.http_metrics.clone(),
+            api_middleware::http_metrics::http_metrics_middleware,
+        ));
+        if state.config.server.metrics_port.is_none() {
+            router = router.route(&state.config.metrics.path, axum::routing::get(health::metrics));
+        }
+    }
+
+    let router = router
+        .with_state(state)
+        // Fallback
+        .fallback(handler_404);
+    
+    Ok(router)
+}
+
+/// The scrape endpoint on a listener of its own, when `server.metrics_port` is set
+/// and metrics are enabled; [`create_app`] then leaves it off the API router.
+pub fn create_metrics_app(state: &AppState) -> Option<Router> {
+    if !state.config.metrics.enabled || state.config.server.metrics_port.is_none() {
+        return None;
+    }
+    Some(
+        Router::new()
+            .route(&state.config.metrics.path, axum::routing::get(health::metrics))
+            .with_state(state.clone()),
+    )
+}
+
+/// Break-glass admin pages; every route first checks the IP allowlist
+fn create_admin_lite_routes(
+    auth_service: &AuthService,
+    config: &erp_core::AdminLiteConfig,
+) -> Result<Router<AppState>, Box<dyn std::error::Error>> {
+    let guard = Arc::new(admin_lite::AdminLiteGuard::new(config)?);
+    let auth_state = erp_auth::AuthState {
+        jwt_service: auth_service.jwt_service(),
+        db: auth_service.db(),
+        redis: auth_service.redis(),
+        tenants: auth_service.tenant_resolver(),
+    };
+    info!("Admin lite pages enabled for {:?}", config.ip_allowlist);
+
+    let pages = admin_lite_handlers::admin_lite_routes()
+        .layer(axum::middleware::from_fn_with_state(guard.clone(), admin_lite::require_admin_middleware))
+        .layer(axum::middleware::from_fn_with_state(auth_state, erp_auth::auth_middleware))
+        .layer(axum::middleware::from_fn(admin_lite::token_cookie_middleware));
+
+    Ok(admin_lite_handlers::admin_lite_public_routes()
+        .merge(pages)
+        .layer(axum::middleware::from_fn_with_state(guard, admin_lite::allowlist_middleware)))
+}
+
+/// Create the API routes
+fn create_api_routes(
+    auth_service: &AuthService,
+    backpressure: &Arc<Backpressure>,
+    existence_checks: &Arc<ExistenceChecks>,
+    idempotency: &Arc<Idempotency>,
+) -> Router<AppState> {
+    let auth_state = erp_auth::AuthState {
+        jwt_service: auth_service.jwt_service(),
+        db: auth_service.db(),
+        redis: auth_service.redis(),
+        tenants: auth_service.tenant_resolver(),
+    };
+
+    Router::new()
+        .nest("/auth", auth::auth_routes())
+        // Build metadata: public so deploy tooling can compare versions
+        .nest("/meta", meta::meta_routes())
+        // Protected routes that require tenant context; customer portal tokens
+        // only reach their customer's reads and never user or role management
+        .nest("/users", users::user_routes()
+            .layer(axum::middleware::from_fn(api_middleware::tenant_context::require_tenant_context))
+            .layer(axum::middleware::from_fn_with_state(auth_state.clone(), erp_auth::portal_guard)))
+        .nest("/roles", roles::role_routes()
+            .layer(axum::middleware::from_fn(api_middleware::tenant_context::require_tenant_context))
+            .layer(axum::middleware::from_fn_with_state(auth_state.clone(), erp_auth::portal_guard)))
+        .nest("/permissions", roles::permission_routes()
+            .layer(axum::middleware::from_fn(api_middleware::tenant_context::require_tenant_context))
+            .layer(axum::middleware::from_fn_with_state(auth_state.clone(), erp_auth::portal_guard)))
+        .nest("/customers", customers::customer_routes(idempotency)
+            .layer(axum::middleware::from_fn(api_middleware::tenant_context::require_tenant_context))
+            .layer(axum::middleware::from_fn_with_state(auth_state.clone(), erp_auth::portal_guard)))
+        // Existence checks for form validation: only whether an email, SKU or customer number is taken
+        .merge(existence_handlers::existence_routes()
+            .layer(axum::middleware::from_fn_with_state(existence_checks.clone(), existence_rate_limit_middleware))
+            .layer(axum::middleware::from_fn(api_middleware::tenant_context::require_tenant_context))
+            .layer(axum::middleware::from_fn_with_state(auth_state.clone(), erp_auth::auth_middleware)))
+        // Customer credit standing for finance integrations: the feed is read by users who
+        // may see customers, open items are imported by those who may change them
+        .merge(credit_handlers::credit_feed_routes()
+            .layer(axum::middleware::from_fn(api_middleware::tenant_context::require_tenant_context))
+            .layer(axum::middleware::from_fn(erp_auth::require_permission("customers:read")))
+            .layer(axum::middleware::from_fn_with_state(auth_state.clone(), erp_auth::auth_middleware)))
+        .merge(credit_handlers::open_item_routes()
+            .layer(axum::middleware::from_fn(api_middleware::tenant_context::require_tenant_context))
+            .layer(axum::middleware::from_fn(erp_auth::require_permission("customers:write")))
+            .layer(axum::middleware::from_fn_with_state(auth_state.clone(), erp_auth::auth_middleware)))
+        // Customer CSV export and re-import: exported by users who may see customers, imported by those who may change them
+        .merge(customer_csv::customer_export_routes()
+            .layer(axum::middleware::from_fn_with_state(backpressure.group(EXPORTS_GROUP), backpressure_middleware))
+            .layer(axum::middleware::from_fn(api_middleware::tenant_context::require_tenant_context))
+            .layer(axum::middleware::from_fn(erp_auth::require_permission("customers:read")))
+            .layer(axum::middleware::from_fn_with_state(auth_state.clone(), erp_auth::auth_middleware)))
+        .merge(customer_csv::customer_import_routes()
+            .layer(axum::middleware::from_fn(api_middleware::tenant_context::require_tenant_context))
+            .layer(axum::middleware::from_fn(erp_auth::require_permission("customers:write")))
+            .layer(axum::middleware::from_fn_with_state(auth_state.clone(), erp_auth::auth_middleware)))
+        // Customer portal accounts: invited and revoked by users who manage customers
+        .merge(portal_users::portal_user_routes()
+            .layer(axum::middleware::from_fn(api_middleware::tenant_context::require_tenant_context))
+            .layer(axum::middleware::from_fn(erp_auth::require_permission("customers:write")))
+            .layer(axum::middleware::from_fn_with_state(auth_state.clone(), erp_auth::auth_middleware)))
+        // Accepting a portal invitation: the invitation token is the credential
+        .merge(portal_users::portal_invitation_routes()
+            .layer(axum::middleware::from_fn(api_middleware::tenant_context::require_tenant_context)))
+        // Accepting a user invitation: the invitation token is the credential
+        .merge(auth::invitation_routes()
+            .layer(axum::middleware::from_fn(api_middleware::tenant_context::require_tenant_context)))
+        // Stocktake imports: scanner files uploaded and submitted by an authenticated user
+        .nest("/inventory/stocktake", inventory::stocktake_routes()
+            .layer(axum::middleware::from_fn(api_middleware::tenant_context::require_tenant_context))
+            .layer(axum::middleware::from_fn(erp_auth::require_permission("inventory:write")))
+            .layer(axum::middleware::from_fn_with_state(auth_state.clone(), erp_auth::auth_middleware)))
+        // Customer returns: received and inspected by an authenticated user
+        .nest("/returns", returns::return_routes()
+            .layer(axum::middleware::from_fn(api_middleware::tenant_context::require_tenant_context))
+            .layer(axum::middleware::from_fn(erp_auth::require_permission("inventory:write")))
+            .layer(axum::middleware::from_fn_with_state(auth_state.clone(), erp_auth::auth_middleware)))
+        // Stock transfers: created and approved by an authenticated user
+        .nest("/inventory/transfers", transfers::transfer_routes()
+            .layer(axum::middleware::from_fn(api_middleware::tenant_context::require_tenant_context))
+            .layer(axum::middleware::from_fn(erp_auth::require_permission("inventory:read")))
+            .layer(axum::middleware::from_fn_with_state(auth_state.clone(), erp_auth::auth_middleware)))
+        // Location capacities: utilization read by inventory users, capacities changed by those who may change inventory
+        .nest("/inventory/capacity", capacity::capacity_routes()
+            .layer(axum::middleware::from_fn(api_middleware::tenant_context::require_tenant_context))
+            .layer(axum::middleware::from_fn(erp_auth::require_permission("inventory:read")))
+            .layer(axum::middleware::from_fn_with_state(auth_state.clone(), erp_auth::auth_middleware)))
+        // Purchase order receipts: history read by inventory users, booked into stock by those who may change inventory
+        .nest("/inventory/purchase-orders", capacity::receiving_routes()
+            .layer(axum::middleware::from_fn(api_middleware::tenant_context::require_tenant_context))
+            .layer(axum::middleware::from_fn(erp_auth::require_permission("inventory:read")))
+            .layer(axum::middleware::from_fn_with_state(auth_state.clone(), erp_auth::auth_middleware)))
+        // Consignment stock: read by inventory users, received, consumed and moved by those who may change inventory
+        .nest("/inventory/consignment", consignment::consignment_routes()
+            .layer(axum::middleware::from_fn(api_middleware::tenant_context::require_tenant_context))
+            .layer(axum::middleware::from_fn(erp_auth::require_permission("inventory:read")))
+            .layer(axum::middleware::from_fn_with_state(auth_state.clone(), erp_auth::auth_middleware)))
+        // Available to promise: read by inventory users
+        .nest("/inventory/atp", atp::atp_routes()
+            .layer(axum::middleware::from_fn(api_middleware::tenant_context::require_tenant_context))
+            .layer(axum::middleware::from_fn(erp_auth::require_permission("inventory:read")))
+            .layer(axum::middleware::from_fn_with_state(auth_state.clone(), erp_auth::auth_middleware)))
+        // Inventory snapshots: read by inventory users, taken and scheduled by those who may change inventory
+        .nest("/inventory/snapshots", inventory_snapshots::inventory_snapshot_routes()
+            .layer(axum::middleware::from_fn(api_middleware::tenant_context::require_tenant_context))
+            .layer(axum::middleware::from_fn(erp_auth::require_permission("inventory:read")))
+            .layer(axum::middleware::from_fn_with_state(auth_state.clone(), erp_auth::auth_middleware)))
+        // Inventory costing: reported to inventory users, settings and restatements by those who may change inventory
+        .nest("/inventory/costing", inventory_costing::inventory_costing_routes()
+            .layer(axum::middleware::from_fn(api_middleware::tenant_context::require_tenant_context))
+            .layer(axum::middleware::from_fn(erp_auth::require_permission("inventory:read")))
+            .layer(axum::middleware::from_fn_with_state(auth_state.clone(), erp_auth::auth_middleware)))
+        // Inventory reports: stated in the tenant's base currency, read by inventory users
+        .nest("/reports", reports::report_routes()
+            .layer(axum::middleware::from_fn_with_state(backpressure.group(REPORTS_GROUP), backpressure_middleware))
+            .layer(axum::middleware::from_fn(api_middleware::tenant_context::require_tenant_context))
+            .layer(axum::middleware::from_fn(erp_auth::require_permission("inventory:read")))
+            .layer(axum::middleware::from_fn_with_state(auth_state.clone(), erp_auth::auth_middleware)))
+        // Pick waves: created, picked and closed by an authenticated user
+        .nest("/inventory/picking", picking::picking_routes()
+            .layer(axum::middleware::from_fn(api_middleware::tenant_context::require_tenant_context))
+            .layer(axum::middleware::from_fn(erp_auth::require_permission("inventory:write")))
+            .layer(axum::middleware::from_fn_with_state(auth_state.clone(), erp_auth::auth_middleware)))
+        // Reserved quantity drift: reported to inventory users, repaired by those who may change inventory
+        .nest("/inventory/reservation-drift", inventory::reservation_drift_routes()
+            .layer(axum::middleware::from_fn(api_middleware::tenant_context::require_tenant_context))
+            .layer(axum::middleware::from_fn(erp_auth::require_permission("inventory:read")))
+            .layer(axum::middleware::from_fn_with_state(auth_state.clone(), erp_auth::auth_middleware)))
+        // Inventory alert stream: a WebSocket read by inventory users
+        .nest("/inventory/alerts", inventory_alert_handlers::inventory_alert_routes()
+            .layer(axum::middleware::from_fn(api_middleware::tenant_context::require_tenant_context))
+            .layer(axum::middleware::from_fn(erp_auth::require_permission("inventory:read")))
+            .layer(axum::middleware::from_fn_with_state(auth_state.clone(), erp_auth::auth_middleware)))
+        .nest("/inventory", inventory::inventory_routes()
+            .layer(axum::middleware::from_fn(api_middleware::tenant_context::require_tenant_context))
+            .layer(axum::middleware::from_fn_with_state(auth_state.clone(), erp_auth::portal_guard)))
+        .nest("/sync", sync_handlers::sync_routes()
+            .layer(axum::middleware::from_fn(api_middleware::tenant_context::require_tenant_context))
+            .layer(axum::middleware::from_fn_with_state(auth_state.clone(), erp_auth::portal_guard)))
+        // Password change for the signed-in user
+        .merge(auth::password_routes()
+            .layer(axum::middleware::from_fn(api_middleware::tenant_context::require_tenant_context))
+            .layer(axum::middleware::from_fn_with_state(auth_state.clone(), erp_auth::auth_middleware)))
+        // Sign-in sessions: the signed-in user's own, and other users' for those who may read or update users
+        .merge(auth::session_routes()
+            .layer(axum::middleware::from_fn(api_middleware::tenant_context::require_tenant_context))
+            .layer(axum::middleware::from_fn_with_state(auth_state.clone(), erp_auth::auth_middleware)))
+        .merge(auth::user_session_read_routes()
+            .layer(axum::middleware::from_fn(api_middleware::tenant_context::require_tenant_context))
+            .layer(axum::middleware::from_fn(erp_auth::require_permission("users:read")))
+            .layer(axum::middleware::from_fn_with_state(auth_state.clone(), erp_auth::auth_middleware)))
+        .merge(auth::user_session_admin_routes()
+            .layer(axum::middleware::from_fn(api_middleware::tenant_context::require_tenant_context))
+            .layer(axum::middleware::from_fn(erp_auth::require_permission("users:update")))
+            .layer(axum::middleware::from_fn_with_state(auth_state.clone(), erp_auth::auth_middleware)))
+        // Customer communication log: scoped to the authenticated user
+        .merge(communications::communication_routes()
+            .layer(axum::middleware::from_fn(api_middleware::tenant_context::require_tenant_context))
+            .layer(axum::middleware::from_fn_with_state(auth_state.clone(), erp_auth::auth_middleware)))
+        // Sales territories and customer assignments: read by users who may see customers,
+        // territories and re-assignments changed by holders of territories:manage
+        .merge(territories::territory_routes()
+            .layer(axum::middleware::from_fn(api_middleware::tenant_context::require_tenant_context))
+            .layer(axum::middleware::from_fn_with_state(auth_state.clone(), erp_auth::auth_middleware)))
+        // Notification delivery preferences of the authenticated user
+        .merge(notifications::notification_routes()
+            .layer(axum::middleware::from_fn(api_middleware::tenant_context::require_tenant_context))
+            .layer(axum::middleware::from_fn_with_state(auth_state.clone(), erp_auth::auth_middleware)))
+        // Base currency and exchange rates: read by any authenticated user, changed by tenant administrators
+        .merge(currency::currency_routes()
+            .layer(axum::middleware::from_fn(api_middleware::tenant_context::require_tenant_context))
+            .layer(axum::middleware::from_fn_with_state(auth_state.clone(), erp_auth::auth_middleware)))
+        .merge(currency::currency_admin_routes()
+            .layer(axum::middleware::from_fn(api_middleware::tenant_context::require_tenant_context))
+            .layer(axum::middleware::from_fn(erp_auth::require_permission("settings:write")))
+            .layer(axum::middleware::from_fn_with_state(auth_state.clone(), erp_auth::auth_middleware)))
+        // Results of requests answered with 202 by a concurrency-limited endpoint, for the user who sent them
+        .merge(async_results::async_result_routes(backpressure.clone())
+            .layer(axum::middleware::from_fn(api_middleware::tenant_context::require_tenant_context))
+            .layer(axum::middleware::from_fn_with_state(auth_state.clone(), erp_auth::auth_middleware)))
+        // Business calendar: read by any authenticated user, changed by tenant administrators
+        .merge(calendar::calendar_routes()
+            .layer(axum::middleware::from_fn(api_middleware::tenant_context::require_tenant_context))
+            .layer(axum::middleware::from_fn_with_state(auth_state.clone(), erp_auth::auth_middleware)))
+        .merge(calendar::calendar_admin_routes()
+            .layer(axum::middleware::from_fn(api_middleware::tenant_context::require_tenant_context))
+            .layer(axum::middleware::from_fn(erp_auth::require_permission("settings:write")))
+            .layer(axum::middleware::from_fn_with_state(auth_state.clone(), erp_auth::auth_middleware)))
+        // Export letterhead: read by any authenticated user, changed by tenant administrators
+        .merge(letterhead_handlers::letterhead_routes()
+            .layer(axum::middleware::from_fn(api_middleware::tenant_context::require_tenant_context))
+            .layer(axum::middleware::from_fn_with_state(auth_state.clone(), erp_auth::auth_middleware)))
+        .merge(letterhead_handlers::letterhead_admin_routes()
+            .layer(axum::middleware::from_fn(api_middleware::tenant_context::require_tenant_context))
+            .layer(axum::middleware::from_fn(erp_auth::require_permission("settings:write")))
+            .layer(axum::middleware::from_fn_with_state(auth_state.clone(), erp_auth::auth_middleware)))
+        // Concurrent session limit: read by any authenticated user, changed by tenant administrators
+        .merge(session_handlers::session_limit_routes()
+            .layer(axum::middleware::from_fn(api_middleware::tenant_context::require_tenant_context))
+            .layer(axum::middleware::from_fn_with_state(auth_state.clone(), erp_auth::auth_middleware)))
+        .merge(session_handlers::session_limit_admin_routes()
+            .layer(axum::middleware::from_fn(api_middleware::tenant_context::require_tenant_context))
+            .layer(axum::middleware::from_fn(erp_auth::require_permission("settings:write")))
+            .layer(axum::middleware::from_fn_with_state(auth_state.clone(), erp_auth::auth_middleware)))
+        // API usage per access token and endpoint group, for tenant administrators
+        .merge(api_usage_handlers::api_usage_routes()
+            .layer(axum::middleware::from_fn(api_middleware::tenant_context::require_tenant_context))
+            .layer(axum::middleware::from_fn(erp_auth::require_permission("settings:write")))
+            .layer(axum::middleware::from_fn_with_state(auth_state.clone(), erp_auth::auth_middleware)))
+        // Plan limit usage with its trend, for tenant administrators
+        .merge(plan_limit_handlers::plan_limit_routes()
+            .layer(axum::middleware::from_fn(api_middleware::tenant_context::require_tenant_context))
+            .layer(axum::middleware::from_fn(erp_auth::require_permission("settings:write")))
+            .layer(axum::middleware::from_fn_with_state(auth_state.clone(), erp_auth::auth_middleware)))
+        // Email template overrides with their preview, for tenant administrators
+        .merge(email_template_handlers::email_template_routes()
+            .layer(axum::middleware::from_fn(api_middleware::tenant_context::require_tenant_context))
+            .layer(axum::middleware::from_fn(erp_auth::require_permission("settings:write")))
+            .layer(axum::middleware::from_fn_with_state(auth_state.clone(), erp_auth::auth_middleware)))
+        // Products and pages of the catalog for users who may see products
+        .merge(products::product_read_routes()
+            .layer(axum::middleware::from_fn(api_middleware::tenant_context::require_tenant_context))
+            .layer(axum::middleware::from_fn(erp_auth::require_permission("products:read")))
+            .layer(axum::middleware::from_fn_with_state(auth_state.clone(), erp_auth::auth_middleware)))
+        // Product feed export for users who may see products
+        .merge(products::product_export_routes()
+            .layer(axum::middleware::from_fn_with_state(backpressure.group(EXPORTS_GROUP), backpressure_middleware))
+            .layer(axum::middleware::from_fn(api_middleware::tenant_context::require_tenant_context))
+            .layer(axum::middleware::from_fn(erp_auth::require_permission("products:read")))
+            .layer(axum::middleware::from_fn_with_state(auth_state.clone(), erp_auth::auth_middleware)))
+        // Supplier catalogs and quotes: read by users who may see suppliers, changed by those who may change them
+        .merge(suppliers::supplier_catalog_routes()
+            .layer(axum::middleware::from_fn(api_middleware::tenant_context::require_tenant_context))
+            .layer(axum::middleware::from_fn(erp_auth::require_permission("suppliers:read")))
+            .layer(axum::middleware::from_fn_with_state(auth_state.clone(), erp_auth::auth_middleware)))
+        .merge(suppliers::supplier_catalog_admin_routes()
+            .layer(axum::middleware::from_fn(api_middleware::tenant_context::require_tenant_context))
+            .layer(axum::middleware::from_fn(erp_auth::require_permission("suppliers:write")))
+            .layer(axum::middleware::from_fn_with_state(auth_state.clone(), erp_auth::auth_middleware)))
+        // Bulk price updates: preview, guarded apply and rollback by an authenticated user
+        .nest("/products", products::product_routes()
+            .layer(axum::middleware::from_fn(api_middleware::tenant_context::require_tenant_context))
+            .layer(axum::middleware::from_fn(erp_auth::require_permission("products:write")))
+            .layer(axum::middleware::from_fn_with_state(auth_state.clone(), erp_auth::auth_middleware)))
+        // Global search: each type is searched only if the user may read it, so no single permission here
+        .nest("/search", search_handlers::search_routes()
+            .layer(axum::middleware::from_fn(api_middleware::tenant_context::require_tenant_context))
+            .layer(axum::middleware::from_fn_with_state(auth_state.clone(), erp_auth::auth_middleware)))
+        // Tenant activity feed: audit, customer and inventory events for tenant administrators
+        .nest("/activity", activity_handlers::activity_routes()
+            .merge(activity_handlers::activity_export_routes()
+                .layer(axum::middleware::from_fn_with_state(backpressure.group(EXPORTS_GROUP), backpressure_middleware)))
+            .layer(axum::middleware::from_fn(api_middleware::tenant_context::require_tenant_context))
+            .layer(axum::middleware::from_fn(erp_auth::require_permission("audit:read")))
+            .layer(axum::middleware::from_fn_with_state(auth_state.clone(), erp_auth::auth_middleware)))
+        // Audit trail of the tenant, filtered and paged
+        .nest("/audit", audit_handlers::audit_routes()
+            .layer(axum::middleware::from_fn(api_middleware::tenant_context::require_tenant_context))
+            .layer(axum::middleware::from_fn(erp_auth::require_permission("audit:read")))
+            .layer(axum::middleware::from_fn_with_state(auth_state.clone(), erp_auth::auth_middleware)))
+        // Tenant webhooks: subscriptions, recent deliveries and the event catalog, for tenant administrators
+        .nest("/webhooks", webhook_handlers::webhook_routes()
+            .layer(axum::middleware::from_fn(api_middleware::tenant_context::require_tenant_context))
+            .layer(axum::middleware::from_fn(erp_auth::require_permission("settings:write")))
+            .layer(axum::middleware::from_fn_with_state(auth_state.clone(), erp_auth::auth_middleware)))
+        // Platform administration: authenticated and restricted to system administrators
+        .nest("/admin", admin::admin_routes()
+            .merge(admin::admin_export_routes()
+                .layer(axum::middleware::from_fn_with_state(backpressure.group(EXPORTS_GROUP), backpressure_middleware)))
+            .layer(axum::middleware::from_fn(erp_auth::require_permission("system:admin")))
+            .layer(axum::middleware::from_fn_with_state(auth_state, erp_auth::auth_middleware)))
+}
+
+async fn handler_404() -> impl IntoResponse {
+    (
+        StatusCode::NOT_FOUND,
+        Json(serde_json::json!({
+            "error": "Resource not found"
+        })),
+    )
+}
+
+/// Connect to Redis; the connection manager reconnects on its own
+pub async fn init_redis(url: &str) -> Result<ConnectionManager, redis::RedisError> {
+    let client = redis::Client::open(url)?;
+    ConnectionManager::new(client).await
+}
+
+/// Apply the migrations bundled with this server, recording them in the migration audit
+pub async fn run_migrations(db: &DatabasePool, environment: &str) -> Result<(), sqlx::Error> {
+    info!("Running database migrations...");
+
+    // Use sqlx migrator to run all migrations in proper order
+    let migrator = sqlx::migrate!("../../migrations");
+    let build = build_info::build_info();
+    let source = erp_core::migration_audit::MigrationSource {
+        applied_by: format!("erp-api {}+{}", build.version, build.commit_short),
+        build_commit: build.commit.to_string(),
+        environment: environment.to_string(),
+    };
+    let mut conn = db.main_pool.acquire().await?;
+    erp_core::migration_audit::run_audited(&mut conn, &migrator, &source).await?;
+
+    info!("Migrations completed successfully");
+    Ok(())
+}
+
+/// Validate configuration to ensure secure defaults are not used in production
+pub fn validate_configuration(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+
+    const DEFAULT_SECRETS: &[&str] = &[
+        "your-super-secret-jwt-key-change-in-production-min-32-chars",
+        "your-32-char-encryption-key-here!",
+        "change_me_in_production",
+        "placeholder",
+        "default",
+        "secret",
+    ];
+
+    const DEFAULT_PASSWORDS: &[&str] = &[
+        "erp_secure_password_change_in_production",
+        "redis_secure_password_change_in_production",
+        "your-app-password",
+        "password123",
+        "admin",
+    ];
+
+    let mut errors = Vec::new();
+
+    // Check environment
+    let is_production = std::env::var("ENVIRONMENT")
+        .unwrap_or_else(|_| "development".to_string())
+        .to_lowercase() == "production";
+
+    // JWT Secret validation
+    if config.jwt.secret.len() < 32 {
+        errors.push("JWT secret must be at least 32 characters long".to_string());
+    }
+
+    if DEFAULT_SECRETS.iter().any(|&s| config.jwt.secret.contains(s)) {
+        errors.push("JWT secret contains default/insecure value".to_string());
+    }
+
+    // AES Encryption Key validation
+    if config.security.aes_encryption_key.len() != 32 {
+        errors.push("AES encryption key must be exactly 32 characters long".to_string());
+    }
+
+    if DEFAULT_SECRETS.iter().any(|&s| config.security.aes_encryption_key.contains(s)) {
+        errors.push("AES encryption key contains default/insecure value".to_string());
+    }
+
+    // Database password validation (if extractable from URL)
+    if let Some(password_start) = config.database.url.find(':') {
+        if let Some(password_section) = config.database.url.get(password_start + 1..) {
+            if let Some(at_sign) = password_section.find('@') {
+                let password_part = &password_section[..at_sign];
+                if let Some(colon) = password_part.rfind(':') {
+                    let password = &password_part[colon + 1..];
+                    if DEFAULT_PASSWORDS.contains(&password) {
+                        errors.push("Database password contains default/insecure value".to_string());
+                    }
+                }
+            }
+        }
+    }
+
+    // Redis password validation (if extractable from URL)
+    if config.redis.url.contains("redis_secure_password_change_in_production") {
+        errors.push("Redis password contains default/insecure value".to_string());
+    }
+
+    // Production-specific validations
+    if is_production {
+        // CORS validation
+        if config.cors.allowed_origins.contains(&"*".to_string()) {
+            errors.push("CORS allowed origins contains wildcard (*) in production".to_string());
+        }
+
+        // Email provider validation
+        if config.email.provider == "mock" {
+            errors.push("Email provider is set to 'mock' in production".to_string());
+        }
+
+        // Debug mode validation
+        if std::env::var("DEBUG_MODE").unwrap_or_default() == "true" {
+            errors.push("DEBUG_MODE is enabled in production".to_string());
+        }
+
+        // Token expiry validation
+        if config.jwt.access_token_expiry > 3600 {
+            errors.push("JWT access token expiry is too long for production (should be ≤ 1 hour)".to_string());
+        }
+    }
+
+    // If there are errors, report them
+    if !errors.is_empty() {
+        eprintln!("\n⚠️  CONFIGURATION SECURITY ISSUES DETECTED ⚠️");
+        eprintln!("================================================");
+        for (i, error) in errors.iter().enumerate() {
+            eprintln!("{}. {}", i + 1, error);
+        }
+        eprintln!("================================================");
+
+        if is_production {
+            eprintln!("\n🛑 REFUSING TO START IN PRODUCTION WITH SECURITY ISSUES");
+            eprintln!("Please fix the above issues before deploying to production.\n");
+            return Err("Configuration validation failed: security issues detected".into());
+        } else {
+            eprintln!("\n⚠️  WARNING: Running with insecure configuration in development mode");
+            eprintln!("These issues MUST be fixed before deploying to production.\n");
+        }
+    } else {
+        info!("✅ Configuration security validation passed");
+    }
+
+    Ok(())
+}
+
+/// Host and process of this instance, recorded with every leased number block
+fn numbering_instance_id() -> String {
+    let host = std::env::var("HOSTNAME").unwrap_or_else(|_| "erp-api".to_string());
+    format!("{}:{}", host, std::process::id())
+}
@@ -46,129 +46,11 @@
 //! - **Health**: http://localhost:3000/health
 //! - **Docs**: http://localhost:3000/swagger-ui
 
-use axum::{
-    Router,
-    http::StatusCode,
-    response::IntoResponse,
-    Json,
-};
-use erp_auth::AuthService;
-use erp_core::{Config, CorsConfig, DatabasePool};
-use redis::aio::ConnectionManager;
-use std::{net::SocketAddr, sync::Arc};
-use tower::ServiceBuilder;
-use tower_http::{
-    compression::CompressionLayer,
-    cors::{CorsLayer, Any},
-    trace::{DefaultMakeSpan, DefaultOnRequest, DefaultOnResponse, TraceLayer},
-};
-use axum::http::{Method, HeaderName, HeaderValue};
-use tracing::{info, Level};
+use erp_api::{build_state, create_app, create_metrics_app, init_redis, run_migrations, validate_configuration, BuildTaggedFormat};
+use erp_core::{Config, DatabasePool};
+use std::net::SocketAddr;
+use tracing::{info, warn};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
-use utoipa::OpenApi;
-use utoipa_swagger_ui::SwaggerUi;
-
-mod error;
-mod error_handler;
-mod handlers;
-mod health;
-mod api_middleware;
-mod state;
-
-use crate::{
-    handlers::{auth, users, roles, customers},
-    state::AppState
-};
-
-/// Builds a CORS layer from configuration settings.
-/// 
-/// This function creates a tower-http CORS layer based on the application's
-/// CORS configuration. It supports both permissive development settings
-/// and restrictive production policies.
-/// 
-/// # Configuration Options
-/// 
-/// - **Origins**: Specific domains or "*" wildcard (development only)
-/// - **Methods**: HTTP methods allowed for cross-origin requests
-/// - **Headers**: Request headers permitted in CORS requests
-/// - **Credentials**: Whether to allow cookies and authorization headers
-/// - **Max Age**: How long browsers cache preflight responses
-/// 
-/// # Security Notes
-/// 
-/// - Production should never use "*" for allowed origins
-/// - Credentials should only be enabled with specific origins
-/// - Headers should be limited to necessary values only
-/// 
-/// # Examples
-/// 
-/// ```rust
-/// let cors_config = CorsConfig {
-///     allowed_origins: vec!["https://myapp.com".to_string()],
-///     allowed_methods: vec!["GET".to_string(), "POST".to_string()],
-///     allowed_headers: vec!["authorization".to_string()],
-///     expose_headers: vec!["x-request-id".to_string()],
-///     allow_credentials: true,
-///     max_age: Some(3600),
-/// };
-/// 
-/// let cors_layer = build_cors_layer(&cors_config)?;
-/// ```
-fn build_cors_layer(cors_config: &CorsConfig) -> Result<CorsLayer, Box<dyn std::error::Error>> {
-    let mut cors = CorsLayer::new();
-    
-    // Configure allowed origins
-    if cors_config.allowed_origins.contains(&"*".to_string()) {
-        cors = cors.allow_origin(Any);
-    } else {
-        let origins: Result<Vec<HeaderValue>, _> = cors_config.allowed_origins
-            .iter()
-            .map(|origin| origin.parse())
-            .collect();
-        cors = cors.allow_origin(origins?);
-    }
-    
-    // Configure allowed methods
-    if cors_config.allowed_methods.contains(&"*".to_string()) {
-        cors = cors.allow_methods(Any);
-    } else {
-        let methods: Result<Vec<Method>, _> = cors_config.allowed_methods
-            .iter()
-            .map(|method| method.parse())
-            .collect();
-        cors = cors.allow_methods(methods?);
-    }
-    
-    // Configure allowed headers
-    if cors_config.allowed_headers.contains(&"*".to_string()) {
-        cors = cors.allow_headers(Any);
-    } else {
-        let headers: Result<Vec<HeaderName>, _> = cors_config.allowed_headers
-            .iter()
-            .map(|header| header.parse())
-            .collect();
-        cors = cors.allow_headers(headers?);
-    }
-    
-    // Configure exposed headers
-    if !cors_config.expose_headers.is_empty() {
-        let expose_headers: Result<Vec<HeaderName>, _> = cors_config.expose_headers
-            .iter()
-            .map(|header| header.parse())
-            .collect();
-        cors = cors.expose_headers(expose_headers?);
-    }
-    
-    // Configure credentials
-    cors = cors.allow_credentials(cors_config.allow_credentials);
-    
-    // Configure max age
-    if let Some(max_age) = cors_config.max_age {
-        cors = cors.max_age(std::time::Duration::from_secs(max_age));
-    }
-    
-    Ok(cors)
-}
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -178,19 +60,23 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     info!("Starting ERP Server...");
 
     // Load configuration
-    let config = Config::load()?;
+    let (config, effective_config) = Config::load_effective()?;
     info!("Configuration loaded successfully");
 
     // Validate configuration security
     validate_configuration(&config)?;
     info!("Configuration validation passed");
 
+    // Record what the server runs with, for support and drift checks between instances
+    effective_config.log();
+    erp_core::effective_config::install(effective_config);
+
     // Initialize database
     let db = DatabasePool::new(config.database.clone()).await?;
     info!("Database pool initialized");
 
     // Run migrations
-    run_migrations(&db).await?;
+    run_migrations(&db, &config.app.environment).await?;
     info!("Database migrations completed");
 
     // Initialize Redis
@@ -198,110 +84,58 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     info!("Redis connection established");
 
     // Initialize services
-    let auth_service = Arc::new(
-        AuthService::new(db.clone(), redis.clone(), config.clone()).await?
-    );
-    info!("Auth service initialized");
-
-    // Create app state
-    let app_state = AppState {
-        config: config.clone(),
-        db,
-        redis,
-        auth_service: auth_service.clone(),
-    };
+    let (app_state, background_jobs) = build_state(config.clone(), db, redis).await?;
+    info!("Services initialized");
+    background_jobs.spawn();
+    let numbering = app_state.numbering.clone();
+    let audit_writer = app_state.auth_service.audit_writer();
 
     // Build the application
+    let auth_service = app_state.auth_service.clone();
+    let metrics_app = create_metrics_app(&app_state);
     let app = create_app(app_state, auth_service)?;
 
+    if let (Some(metrics_app), Some(port)) = (metrics_app, config.server.metrics_port) {
+        let addr = SocketAddr::from(([0, 0, 0, 0], port));
+        info!("Metrics listening on {}", addr);
+        let listener = tokio::net::TcpListener::bind(addr).await?;
+        tokio::spawn(async move {
+            if let Err(e) = axum::serve(listener, metrics_app)
+                .with_graceful_shutdown(shutdown_signal())
+                .await
+            {
+                warn!("Metrics server stopped: {}", e);
+            }
+        });
+    }
+
     // Start the server
     let addr = SocketAddr::from(([0, 0, 0, 0], config.server.port));
     info!("Server listening on {}", addr);
 
     let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app)
+    // Peer addresses feed the admin lite IP allowlist
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
         .with_graceful_shutdown(shutdown_signal())
         .await?;
 
-    info!("Server shutdown complete");
-    Ok(())
-}
-
-fn create_app(state: AppState, _auth_service: Arc<AuthService>) -> Result<Router, Box<dyn std::error::Error>> {
-    // OpenAPI documentation
-    #[derive(OpenApi)]
-    #[openapi(
-        paths(
-            health::health_check,
-            health::readiness_check,
-        ),
-        components(schemas()),
-        tags(
-            (name = "health", description = "Health check endpoints"),
-            (name = "auth", description = "Authentication and authorization"),
-            (name = "users", description = "User management"),
-            (name = "roles", description = "Role and permission management"),
-        )
-    )]
-    struct ApiDoc;
-
-    // Build the router
-    let router = Router::new()
-        // API routes
-        .nest("/api/v1", create_api_routes())
-        // Swagger UI
-        .merge(SwaggerUi::new("/swagger-ui").url("/api-docs/openapi.json", ApiDoc::openapi()))
-        // Health checks
-        .route("/health", axum::routing::get(health::health_check))
-        .route("/ready", axum::routing::get(health::readiness_check))
-        // Global middleware (Order matters: layers are applied from bottom to top)
-        .layer(
-            ServiceBuilder::new()
-                // Security headers (applied to all responses)
-                .layer(axum::middleware::from_fn(api_middleware::security_headers::security_headers_middleware))
-                // Request ID middleware
-                .layer(axum::middleware::from_fn(api_middleware::request_id::request_id_middleware))
-                // Tenant context extraction
-                .layer(axum::middleware::from_fn(api_middleware::tenant_context::tenant_context_middleware))
-                // Logging and tracing
-                .layer(
-                    TraceLayer::new_for_http()
-                        .make_span_with(DefaultMakeSpan::new().level(Level::INFO))
-                        .on_request(DefaultOnRequest::new().level(Level::INFO))
-                        .on_response(DefaultOnResponse::new().level(Level::INFO)),
-                )
-                // Response compression
-                .layer(CompressionLayer::new())
-                // CORS (should be outermost)
-                .layer(build_cors_layer(&state.config.cors)?),
-        )
-        .with_state(state)
-        // Fallback
-        .fallback(handler_404);
-    
-    Ok(router)
-}
+    // Unused leased numbers go back to their sequences instead of becoming gaps
+    match numbering.release_all().await {
+        Ok(returned) => info!("Returned {} unused document numbers", returned),
+        Err(e) => warn!("Failed to return unused document numbers: {}", e),
+    }
 
-/// Create the API routes
-fn create_api_routes() -> Router<AppState> {
-    Router::new()
-        .nest("/auth", auth::auth_routes())
-        // Protected routes that require tenant context
-        .nest("/users", users::user_routes()
-            .layer(axum::middleware::from_fn(api_middleware::tenant_context::require_tenant_context)))
-        .nest("/roles", roles::role_routes()
-            .layer(axum::middleware::from_fn(api_middleware::tenant_context::require_tenant_context)))
-        .nest("/customers", customers::customer_routes()
-            .layer(axum::middleware::from_fn(api_middleware::tenant_context::require_tenant_context)))
-}
+    // Buffered audit events are written before the process exits
+    if let Some(audit_writer) = audit_writer {
+        let timeout = std::time::Duration::from_secs(config.audit_writer.shutdown_timeout_secs);
+        match audit_writer.flush(timeout).await {
+            Ok(()) => info!("Audit buffer written"),
+            Err(e) => warn!("Failed to write the audit buffer: {}", e),
+        }
+    }
 
-async fn handler_404() -> impl IntoResponse {
-    (
-        StatusCode::NOT_FOUND,
-        Json(serde_json::json!({
-            "error": "Resource not found"
-        })),
-    )
+    info!("Server shutdown complete");
+    Ok(())
 }
 
 fn init_tracing() {
@@ -310,137 +144,11 @@ fn init_tracing() {
             tracing_subscriber::EnvFilter::try_from_default_env()
                 .unwrap_or_else(|_| "erp_api=debug,erp_auth=debug,erp_core=debug,tower_http=debug".into()),
         )
-        .with(tracing_subscriber::fmt::layer())
+        .with(tracing_subscriber::fmt::layer()
+            .event_format(BuildTaggedFormat::new(tracing_subscriber::fmt::format())))
         .init();
 }
 
-async fn init_redis(url: &str) -> Result<ConnectionManager, redis::RedisError> {
-    let client = redis::Client::open(url)?;
-    ConnectionManager::new(client).await
-}
-
-async fn run_migrations(db: &DatabasePool) -> Result<(), sqlx::Error> {
-    info!("Running database migrations...");
-
-    // Use sqlx migrator to run all migrations in proper order
-    let migrator = sqlx::migrate!("../../migrations");
-    migrator.run(&db.main_pool).await?;
-
-    info!("Migrations completed successfully");
-    Ok(())
-}
-
-/// Validate configuration to ensure secure defaults are not used in production
-fn validate_configuration(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
-
-    const DEFAULT_SECRETS: &[&str] = &[
-        "your-super-secret-jwt-key-change-in-production-min-32-chars",
-        "your-32-char-encryption-key-here!",
-        "change_me_in_production",
-        "placeholder",
-        "default",
-        "secret",
-    ];
-
-    const DEFAULT_PASSWORDS: &[&str] = &[
-        "erp_secure_password_change_in_production",
-        "redis_secure_password_change_in_production",
-        "your-app-password",
-        "password123",
-        "admin",
-    ];
-
-    let mut errors = Vec::new();
-
-    // Check environment
-    let is_production = std::env::var("ENVIRONMENT")
-        .unwrap_or_else(|_| "development".to_string())
-        .to_lowercase() == "production";
-
-    // JWT Secret validation
-    if config.jwt.secret.len() < 32 {
-        errors.push("JWT secret must be at least 32 characters long".to_string());
-    }
-
-    if DEFAULT_SECRETS.iter().any(|&s| config.jwt.secret.contains(s)) {
-        errors.push("JWT secret contains default/insecure value".to_string());
-    }
-
-    // AES Encryption Key validation
-    if config.security.aes_encryption_key.len() != 32 {
-        errors.push("AES encryption key must be exactly 32 characters long".to_string());
-    }
-
-    if DEFAULT_SECRETS.iter().any(|&s| config.security.aes_encryption_key.contains(s)) {
-        errors.push("AES encryption key contains default/insecure value".to_string());
-    }
-
-    // Database password validation (if extractable from URL)
-    if let Some(password_start) = config.database.url.find(':') {
-        if let Some(password_section) = config.database.url.get(password_start + 1..) {
-            if let Some(at_sign) = password_section.find('@') {
-                let password_part = &password_section[..at_sign];
-                if let Some(colon) = password_part.rfind(':') {
-                    let password = &password_part[colon + 1..];
-                    if DEFAULT_PASSWORDS.iter().any(|&p| password == p) {
-                        errors.push("Database password contains default/insecure value".to_string());
-                    }
-                }
-            }
-        }
-    }
-
-    // Redis password validation (if extractable from URL)
-    if config.redis.url.contains("redis_secure_password_change_in_production") {
-        errors.push("Redis password contains default/insecure value".to_string());
-    }
-
-    // Production-specific validations
-    if is_production {
-        // CORS validation
-        if config.cors.allowed_origins.contains(&"*".to_string()) {
-            errors.push("CORS allowed origins contains wildcard (*) in production".to_string());
-        }
-
-        // Email provider validation
-        if config.email.provider == "mock" {
-            errors.push("Email provider is set to 'mock' in production".to_string());
-        }
-
-        // Debug mode validation
-        if std::env::var("DEBUG_MODE").unwrap_or_default() == "true" {
-            errors.push("DEBUG_MODE is enabled in production".to_string());
-        }
-
-        // Token expiry validation
-        if config.jwt.access_token_expiry > 3600 {
-            errors.push("JWT access token expiry is too long for production (should be ≤ 1 hour)".to_string());
-        }
-    }
-
-    // If there are errors, report them
-    if !errors.is_empty() {
-        eprintln!("\n⚠️  CONFIGURATION SECURITY ISSUES DETECTED ⚠️");
-        eprintln!("================================================");
-        for (i, error) in errors.iter().enumerate() {
-            eprintln!("{}. {}", i + 1, error);
-        }
-        eprintln!("================================================");
-
-        if is_production {
-            eprintln!("\n🛑 REFUSING TO START IN PRODUCTION WITH SECURITY ISSUES");
-            eprintln!("Please fix the above issues before deploying to production.\n");
-            return Err("Configuration validation failed: security issues detected".into());
-        } else {
-            eprintln!("\n⚠️  WARNING: Running with insecure configuration in development mode");
-            eprintln!("These issues MUST be fixed before deploying to production.\n");
-        }
-    } else {
-        info!("✅ Configuration security validation passed");
-    }
-
-    Ok(())
-}
 
 async fn shutdown_signal() {
     use tokio::signal;
@@ -470,4 +178,4 @@ async fn shutdown_signal() {
             info!("Received terminate signal");
         },
     }
-}
\ No newline at end of file
+}
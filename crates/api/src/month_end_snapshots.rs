@@ -0,0 +1,264 @@
+//! # Month-End Inventory Snapshots
+//!
+//! Daily job taking the month-end snapshots tenants scheduled per location
+//! (see [`erp_master_data::inventory::snapshot`]). It starts at
+//! `month_end_snapshots.run_at_hour_utc` and, on the last day of a month,
+//! snapshots the location of each enabled schedule with the schedule's
+//! scope. A schedule is marked with the month end once its snapshot is
+//! taken, so a restart on the same day does not take it twice; a failed
+//! snapshot is tried again at the next run of that day only.
+
+use chrono::{NaiveDate, Utc};
+use erp_core::{Error, ErrorCode, MonthEndSnapshotConfig, Result};
+use erp_master_data::inventory::{InventorySnapshotRepository, SnapshotRun, SnapshotTrigger};
+use serde::Serialize;
+use std::sync::Arc;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::retention::next_run;
+
+/// What one run of the job did
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MonthEndRun {
+    pub snapshots: Vec<SnapshotRun>,
+    /// Tenant and location of each schedule whose snapshot failed
+    pub failed: Vec<(Uuid, Uuid)>,
+}
+
+fn inventory_error(e: erp_master_data::MasterDataError) -> Error {
+    Error::new(ErrorCode::DatabaseError, e.to_string())
+}
+
+/// Takes the scheduled month-end snapshots of every tenant
+pub struct MonthEndSnapshotService {
+    repository: Arc<dyn InventorySnapshotRepository>,
+    config: MonthEndSnapshotConfig,
+}
+
+impl MonthEndSnapshotService {
+    pub fn new(repository: Arc<dyn InventorySnapshotRepository>, config: MonthEndSnapshotConfig) -> Self {
+        Self { repository, config }
+    }
+
+    /// Take the snapshots of every schedule due on `today`
+    pub async fn run(&self, today: NaiveDate) -> Result<MonthEndRun> {
+        let schedules = self.repository.list_schedules(None).await.map_err(inventory_error)?;
+        let mut run = MonthEndRun::default();
+
+        for schedule in schedules.iter().filter(|schedule| schedule.is_due(today)) {
+            let snapshot = self
+                .repository
+                .create_snapshot(
+                    schedule.tenant_id,
+                    schedule.location_id,
+                    today,
+                    &schedule.scope,
+                    SnapshotTrigger::MonthEnd,
+                    None,
+                )
+                .await;
+            match snapshot {
+                Ok(snapshot) => {
+                    if let Err(e) = self
+                        .repository
+                        .mark_schedule_run(schedule.tenant_id, schedule.location_id, today)
+                        .await
+                    {
+                        warn!("Failed to mark month-end snapshot of location {} as taken: {}", schedule.location_id, e);
+                    }
+                    run.snapshots.push(snapshot);
+                }
+                Err(e) => {
+                    warn!(
+                        "Month-end snapshot of location {} of tenant {} failed: {}",
+                        schedule.location_id, schedule.tenant_id, e
+                    );
+                    run.failed.push((schedule.tenant_id, schedule.location_id));
+                }
+            }
+        }
+        Ok(run)
+    }
+
+    /// Run every day at `run_at_hour_utc` in the background
+    pub fn spawn(self: &Arc<Self>) -> Option<tokio::task::JoinHandle<()>> {
+        if !self.config.enabled {
+            return None;
+        }
+
+        let service = Arc::clone(self);
+        Some(tokio::spawn(async move {
+            loop {
+                let now = Utc::now();
+                let wait = (next_run(now, service.config.run_at_hour_utc) - now)
+                    .to_std()
+                    .unwrap_or_default();
+                tokio::time::sleep(wait).await;
+
+                match service.run(Utc::now().date_naive()).await {
+                    Ok(run) if run.snapshots.is_empty() && run.failed.is_empty() => {}
+                    Ok(run) => info!(
+                        "Month-end snapshots: {} taken, {} failed",
+                        run.snapshots.len(),
+                        run.failed.len()
+                    ),
+                    Err(e) => warn!("Month-end snapshot run failed: {}", e),
+                }
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use erp_master_data::inventory::{ABCClassification, SnapshotLine, SnapshotSchedule, SnapshotScope};
+    use std::sync::Mutex;
+
+    type MasterDataResult<T> = erp_master_data::Result<T>;
+
+    /// Stock and schedules in memory; snapshots keep the lines the scope matches
+    #[derive(Default)]
+    struct MemorySnapshots {
+        stock: Vec<(Uuid, SnapshotLine)>,
+        schedules: Mutex<Vec<SnapshotSchedule>>,
+        runs: Mutex<Vec<(SnapshotRun, Vec<SnapshotLine>)>>,
+    }
+
+    #[async_trait]
+    impl InventorySnapshotRepository for MemorySnapshots {
+        async fn create_snapshot(
+            &self,
+            tenant_id: Uuid,
+            location_id: Uuid,
+            snapshot_date: NaiveDate,
+            scope: &SnapshotScope,
+            trigger: SnapshotTrigger,
+            created_by: Option<Uuid>,
+        ) -> MasterDataResult<SnapshotRun> {
+            let lines: Vec<SnapshotLine> = self
+                .stock
+                .iter()
+                .filter(|(location, line)| *location == location_id && scope.matches(line))
+                .map(|(_, line)| line.clone())
+                .collect();
+            let run = SnapshotRun {
+                id: Uuid::new_v4(),
+                tenant_id,
+                location_id,
+                snapshot_date,
+                scope: scope.clone(),
+                partial: !scope.is_full(),
+                trigger,
+                line_count: lines.len() as i64,
+                total_value: lines.iter().map(|line| line.total_value).sum(),
+                created_by,
+                created_at: Utc::now(),
+            };
+            self.runs.lock().unwrap().push((run.clone(), lines));
+            Ok(run)
+        }
+
+        async fn get_snapshot(&self, _tenant_id: Uuid, snapshot_id: Uuid) -> MasterDataResult<Option<SnapshotRun>> {
+            Ok(self.runs.lock().unwrap().iter().find(|(run, _)| run.id == snapshot_id).map(|(run, _)| run.clone()))
+        }
+
+        async fn list_snapshots(&self, _tenant_id: Uuid, _location_id: Option<Uuid>, _limit: i64) -> MasterDataResult<Vec<SnapshotRun>> {
+            Ok(self.runs.lock().unwrap().iter().map(|(run, _)| run.clone()).collect())
+        }
+
+        async fn get_snapshot_lines(&self, _tenant_id: Uuid, snapshot_id: Uuid) -> MasterDataResult<Vec<SnapshotLine>> {
+            Ok(self
+                .runs
+                .lock()
+                .unwrap()
+                .iter()
+                .find(|(run, _)| run.id == snapshot_id)
+                .map(|(_, lines)| lines.clone())
+                .unwrap_or_default())
+        }
+
+        async fn list_schedules(&self, _tenant_id: Option<Uuid>) -> MasterDataResult<Vec<SnapshotSchedule>> {
+            Ok(self.schedules.lock().unwrap().clone())
+        }
+
+        async fn save_schedule(&self, schedule: &SnapshotSchedule) -> MasterDataResult<SnapshotSchedule> {
+            self.schedules.lock().unwrap().push(schedule.clone());
+            Ok(schedule.clone())
+        }
+
+        async fn delete_schedule(&self, _tenant_id: Uuid, _location_id: Uuid) -> MasterDataResult<bool> {
+            Ok(false)
+        }
+
+        async fn mark_schedule_run(&self, tenant_id: Uuid, location_id: Uuid, month_end: NaiveDate) -> MasterDataResult<()> {
+            for schedule in self.schedules.lock().unwrap().iter_mut() {
+                if schedule.tenant_id == tenant_id && schedule.location_id == location_id {
+                    schedule.last_run_on = Some(month_end);
+                }
+            }
+            Ok(())
+        }
+    }
+
+    fn line(abc_class: ABCClassification, unit_cost: i64) -> SnapshotLine {
+        SnapshotLine {
+            product_id: Uuid::new_v4(),
+            category_id: None,
+            abc_class: Some(abc_class),
+            quantity_on_hand: 10,
+            quantity_reserved: 0,
+            quantity_on_order: 0,
+            quantity_in_transit: 0,
+            unit_cost: Some(unit_cost),
+            total_value: unit_cost * 10,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_scheduled_month_end_snapshot_takes_only_its_scope() {
+        let (tenant, warehouse, store) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let mut stock: Vec<(Uuid, SnapshotLine)> = (0..3).map(|_| (warehouse, line(ABCClassification::A, 90_000))).collect();
+        stock.extend((0..40).map(|_| (warehouse, line(ABCClassification::C, 150))));
+        stock.push((store, line(ABCClassification::A, 90_000)));
+        let repository = Arc::new(MemorySnapshots { stock, ..Default::default() });
+
+        let class_a = SnapshotScope { abc_class: Some(ABCClassification::A), ..Default::default() };
+        for (location_id, enabled) in [(warehouse, true), (store, false)] {
+            repository
+                .save_schedule(&SnapshotSchedule {
+                    tenant_id: tenant,
+                    location_id,
+                    scope: class_a.clone(),
+                    enabled,
+                    last_run_on: None,
+                    updated_by: None,
+                    updated_at: Utc::now(),
+                })
+                .await
+                .unwrap();
+        }
+        let service = MonthEndSnapshotService::new(repository.clone(), MonthEndSnapshotConfig::default());
+        let day = |m, d| NaiveDate::from_ymd_opt(2025, m, d).unwrap();
+
+        // Nothing before the month end
+        assert!(service.run(day(4, 29)).await.unwrap().snapshots.is_empty());
+
+        let run = service.run(day(4, 30)).await.unwrap();
+        assert_eq!(run.snapshots.len(), 1, "the disabled schedule takes no snapshot");
+        let snapshot = &run.snapshots[0];
+        assert_eq!(snapshot.location_id, warehouse);
+        assert_eq!(snapshot.line_count, 3);
+        assert!(snapshot.partial);
+        assert_eq!(snapshot.scope, class_a);
+        assert_eq!(snapshot.trigger, SnapshotTrigger::MonthEnd);
+        assert_eq!(snapshot.total_value, 3 * 900_000);
+
+        // Taken once per month end, even if the job runs again that day
+        assert!(service.run(day(4, 30)).await.unwrap().snapshots.is_empty());
+        assert_eq!(repository.schedules.lock().unwrap()[0].last_run_on, Some(day(4, 30)));
+        assert_eq!(service.run(day(5, 31)).await.unwrap().snapshots.len(), 1);
+    }
+}
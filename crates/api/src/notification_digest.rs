@@ -0,0 +1,1131 @@
+//! # Notification Digests
+//!
+//! Non-urgent notifications can be collected into one summary email a day
+//! instead of being emailed one by one. [`NotificationService::notify`] is the
+//! entry point for every user notification:
+//!
+//! - `critical` notifications are always emailed right away
+//! - otherwise a category the user turned off is dropped, and the user's
+//!   delivery choice for the category decides (`delivery` in
+//!   `public.notification_preferences`); without one, categories listed in
+//!   `notification_digest.digest_categories` go to the digest and the rest
+//!   are emailed right away
+//! - digest notifications wait in `public.pending_digest_notifications`
+//!
+//! Each tenant's digest goes out once a day at the tenant settings
+//! `notification_digest.send_time` (`HH:MM`) in `notification_digest.timezone`
+//! (an IANA name such as `Europe/Berlin`), defaulting to
+//! `notification_digest.default_send_time` and `default_timezone`. Local time
+//! is computed by PostgreSQL, so daylight saving time moves the send time with
+//! the wall clock. With `notification_digest.calendar_policy` set to
+//! `working_days_only` or `skip_holidays` there is no digest on the tenant's
+//! non-working days (see [`crate::business_calendar`]); the items wait for the
+//! next working day's digest.
+//!
+//! A run claims the tenant and local date in `notification_digest_runs`, emails
+//! every user with pending items one summary grouped by category and then by
+//! location or customer, and marks exactly the items of each email as sent
+//! right after it went out. Items whose email failed, and items that arrived
+//! during the run, stay pending for the next digest. A run that died half-way
+//! is claimed again after [`STALE_RUN_MINUTES`] and sends only what is still
+//! pending, so nobody gets the same item twice.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use erp_auth::{email::EmailReference, AuthService, EmailService};
+use erp_core::{Error, ErrorCode, NotificationDigestConfig, Result};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Row};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::business_calendar::{calendar_around, CalendarStore};
+use crate::follow_up_reminders::{html_escape, user_email};
+
+/// A claimed run not completed within this time is considered dead
+pub const STALE_RUN_MINUTES: i64 = 30;
+
+/// Tenant setting with the local send time, `HH:MM`
+pub const SEND_TIME_SETTING: &str = "notification_digest.send_time";
+
+/// Tenant setting with the IANA timezone of the send time
+pub const TIMEZONE_SETTING: &str = "notification_digest.timezone";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationSeverity {
+    Info,
+    Warning,
+    Critical,
+}
+
+impl NotificationSeverity {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            NotificationSeverity::Info => "info",
+            NotificationSeverity::Warning => "warning",
+            NotificationSeverity::Critical => "critical",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "info" => Some(NotificationSeverity::Info),
+            "warning" => Some(NotificationSeverity::Warning),
+            "critical" => Some(NotificationSeverity::Critical),
+            _ => None,
+        }
+    }
+}
+
+/// How a user wants a category of notifications delivered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryMode {
+    Immediate,
+    Digest,
+}
+
+impl DeliveryMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DeliveryMode::Immediate => "immediate",
+            DeliveryMode::Digest => "digest",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "immediate" => Some(DeliveryMode::Immediate),
+            "digest" => Some(DeliveryMode::Digest),
+            _ => None,
+        }
+    }
+}
+
+/// A notification for one user
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notification {
+    pub tenant_id: Uuid,
+    pub user_id: Uuid,
+    /// Notification type as in `notification_preferences`, e.g. `inventory_alert`
+    pub category: String,
+    pub severity: NotificationSeverity,
+    pub title: String,
+    pub body: Option<String>,
+    pub location_id: Option<Uuid>,
+    pub location_name: Option<String>,
+    pub customer_id: Option<Uuid>,
+    pub customer_name: Option<String>,
+    /// Path in the web app, e.g. `/inventory/alerts/<id>`
+    pub link: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A notification waiting for the digest
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingNotification {
+    pub id: i64,
+    pub notification: Notification,
+}
+
+/// A user's settings for one category
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct NotificationPreference {
+    pub email_enabled: bool,
+    /// `None` until the user picks one
+    pub delivery: Option<DeliveryMode>,
+}
+
+impl Default for NotificationPreference {
+    fn default() -> Self {
+        Self {
+            email_enabled: true,
+            delivery: None,
+        }
+    }
+}
+
+/// What happened to a notification
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Delivery {
+    Sent,
+    Queued,
+    Skipped,
+}
+
+/// A tenant with pending digest items, as seen on its own wall clock
+#[derive(Debug, Clone, PartialEq)]
+pub struct TenantDigestClock {
+    pub tenant_id: Uuid,
+    pub local_now: NaiveDateTime,
+    /// Raw `notification_digest.send_time` setting, if any
+    pub send_time: Option<String>,
+    /// Local date of the last completed digest
+    pub last_digest_on: Option<NaiveDate>,
+}
+
+/// Whether a notification is emailed now, collected for the digest, or dropped
+pub fn delivery_for(
+    notification: &Notification,
+    preference: &NotificationPreference,
+    digest_categories: &[String],
+) -> Delivery {
+    if notification.severity == NotificationSeverity::Critical {
+        return Delivery::Sent;
+    }
+    if !preference.email_enabled {
+        return Delivery::Skipped;
+    }
+    let mode = preference.delivery.unwrap_or_else(|| {
+        if digest_categories.iter().any(|c| c == &notification.category) {
+            DeliveryMode::Digest
+        } else {
+            DeliveryMode::Immediate
+        }
+    });
+    match mode {
+        DeliveryMode::Immediate => Delivery::Sent,
+        DeliveryMode::Digest => Delivery::Queued,
+    }
+}
+
+/// `HH:MM` send time
+pub fn parse_send_time(value: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(value.trim(), "%H:%M").ok()
+}
+
+/// Local date whose digest is due at `local_now`: from `send_time` on, once per day
+pub fn digest_due(local_now: NaiveDateTime, send_time: NaiveTime, last_digest_on: Option<NaiveDate>) -> Option<NaiveDate> {
+    let today = local_now.date();
+    if local_now.time() < send_time || last_digest_on.is_some_and(|last| last >= today) {
+        return None;
+    }
+    Some(today)
+}
+
+/// Notifications of one category about the same location or customer
+#[derive(Debug, Clone, PartialEq)]
+pub struct DigestGroup<'a> {
+    pub label: String,
+    pub items: Vec<&'a PendingNotification>,
+}
+
+/// Notifications of one category
+#[derive(Debug, Clone, PartialEq)]
+pub struct DigestSection<'a> {
+    pub category: String,
+    pub count: usize,
+    pub groups: Vec<DigestGroup<'a>>,
+}
+
+/// Group one user's items by category, then by location or customer. Sections
+/// and groups are sorted by name, items without either come last as "Other".
+pub fn group_digest(items: &[PendingNotification]) -> Vec<DigestSection<'_>> {
+    // (0, name) for locations, (1, name) for customers, (2, "") for the rest
+    let mut sections: BTreeMap<&str, BTreeMap<(u8, String), Vec<&PendingNotification>>> = BTreeMap::new();
+    for item in items {
+        let n = &item.notification;
+        let key = match (n.location_id, n.customer_id) {
+            (Some(id), _) => (0, n.location_name.clone().unwrap_or_else(|| id.to_string())),
+            (None, Some(id)) => (1, n.customer_name.clone().unwrap_or_else(|| id.to_string())),
+            (None, None) => (2, String::new()),
+        };
+        sections.entry(&n.category).or_default().entry(key).or_default().push(item);
+    }
+
+    sections
+        .into_iter()
+        .map(|(category, groups)| DigestSection {
+            category: category.to_string(),
+            count: groups.values().map(Vec::len).sum(),
+            groups: groups
+                .into_iter()
+                .map(|((kind, name), items)| DigestGroup {
+                    label: match kind {
+                        0 => format!("Location {}", name),
+                        1 => format!("Customer {}", name),
+                        _ => "Other".to_string(),
+                    },
+                    items,
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+/// `inventory_alert` -> `Inventory alert`
+fn category_label(category: &str) -> String {
+    let spaced = category.replace('_', " ");
+    let mut chars = spaced.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => spaced,
+    }
+}
+
+fn deep_link(base_url: &str, link: &str) -> String {
+    if link.starts_with("http://") || link.starts_with("https://") {
+        link.to_string()
+    } else {
+        format!("{}/{}", base_url.trim_end_matches('/'), link.trim_start_matches('/'))
+    }
+}
+
+/// Subject, HTML and text body of a user's digest for `date`
+pub fn render_digest(sections: &[DigestSection<'_>], date: NaiveDate, base_url: &str) -> (String, String, String) {
+    let total: usize = sections.iter().map(|s| s.count).sum();
+    let subject = format!("Daily summary for {}: {} notification(s)", date.format("%Y-%m-%d"), total);
+
+    let mut text = String::new();
+    let mut html = format!("<h2>{}</h2>\n", html_escape(&subject));
+    for section in sections {
+        let heading = format!("{} ({})", category_label(&section.category), section.count);
+        text.push_str(&format!("{}\n", heading));
+        html.push_str(&format!("<h3>{}</h3>\n", html_escape(&heading)));
+
+        for group in &section.groups {
+            let label = format!("{} ({})", group.label, group.items.len());
+            text.push_str(&format!("  {}\n", label));
+            html.push_str(&format!("<h4>{}</h4>\n<ul>\n", html_escape(&label)));
+
+            for item in &group.items {
+                let n = &item.notification;
+                let link = n.link.as_deref().map(|l| deep_link(base_url, l));
+                let marker = if n.severity == NotificationSeverity::Warning { " [warning]" } else { "" };
+
+                text.push_str(&format!("  - {}{}", n.title, marker));
+                if let Some(link) = &link {
+                    text.push_str(&format!(" {}", link));
+                }
+                text.push('\n');
+
+                let title = html_escape(&format!("{}{}", n.title, marker));
+                match &link {
+                    Some(link) => html.push_str(&format!("<li><a href=\"{}\">{}</a>", html_escape(link), title)),
+                    None => html.push_str(&format!("<li>{}", title)),
+                }
+                if let Some(body) = &n.body {
+                    html.push_str(&format!("<br>{}", html_escape(body)));
+                }
+                html.push_str("</li>\n");
+            }
+            html.push_str("</ul>\n");
+        }
+    }
+
+    (subject, html, text)
+}
+
+/// Preferences, pending items and digest runs
+#[async_trait]
+pub trait NotificationStore: Send + Sync {
+    async fn preference(&self, tenant_id: Uuid, user_id: Uuid, category: &str) -> Result<NotificationPreference>;
+
+    /// Every category the user has a preference for
+    async fn preferences(&self, tenant_id: Uuid, user_id: Uuid) -> Result<Vec<(String, NotificationPreference)>>;
+
+    async fn set_delivery(&self, tenant_id: Uuid, user_id: Uuid, category: &str, delivery: DeliveryMode) -> Result<()>;
+
+    /// `None` if the user or tenant is unknown or inactive
+    async fn email_address(&self, tenant_id: Uuid, user_id: Uuid) -> Result<Option<String>>;
+
+    /// Store a notification for the digest; returns its id
+    async fn enqueue(&self, notification: &Notification) -> Result<i64>;
+
+    /// Active tenants with pending items; local time in the tenant's timezone,
+    /// `default_timezone` if it has none or an unknown one
+    async fn digest_clocks(&self, now: DateTime<Utc>, default_timezone: &str) -> Result<Vec<TenantDigestClock>>;
+
+    /// Claim the tenant's run for `date`; false while another run holds it,
+    /// i.e. it is completed or was claimed after `stale_before`
+    async fn claim_run(&self, tenant_id: Uuid, date: NaiveDate, now: DateTime<Utc>, stale_before: DateTime<Utc>) -> Result<bool>;
+
+    async fn complete_run(&self, tenant_id: Uuid, date: NaiveDate, now: DateTime<Utc>) -> Result<()>;
+
+    /// Unsent items of the tenant, oldest first
+    async fn pending(&self, tenant_id: Uuid) -> Result<Vec<PendingNotification>>;
+
+    /// Mark those of `ids` not sent yet as sent with the digest of `date`; returns rows marked
+    async fn mark_sent(&self, ids: &[i64], date: NaiveDate, now: DateTime<Utc>) -> Result<u64>;
+}
+
+pub struct PostgresNotificationStore {
+    pool: PgPool,
+    auth_service: Arc<AuthService>,
+}
+
+impl PostgresNotificationStore {
+    pub fn new(pool: PgPool, auth_service: Arc<AuthService>) -> Self {
+        Self { pool, auth_service }
+    }
+}
+
+fn preference_from_row(row: &sqlx::postgres::PgRow) -> NotificationPreference {
+    NotificationPreference {
+        email_enabled: row.get("email_enabled"),
+        delivery: row.get::<Option<String>, _>("delivery").as_deref().and_then(DeliveryMode::parse),
+    }
+}
+
+#[async_trait]
+impl NotificationStore for PostgresNotificationStore {
+    async fn preference(&self, tenant_id: Uuid, user_id: Uuid, category: &str) -> Result<NotificationPreference> {
+        let row = sqlx::query(
+            "SELECT email_enabled, delivery FROM public.notification_preferences \
+             WHERE tenant_id = $1 AND user_id = $2 AND notification_type = $3",
+        )
+        .bind(tenant_id)
+        .bind(user_id)
+        .bind(category)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.as_ref().map(preference_from_row).unwrap_or_default())
+    }
+
+    async fn preferences(&self, tenant_id: Uuid, user_id: Uuid) -> Result<Vec<(String, NotificationPreference)>> {
+        let rows = sqlx::query(
+            "SELECT notification_type, email_enabled, delivery FROM public.notification_preferences \
+             WHERE tenant_id = $1 AND user_id = $2 ORDER BY notification_type",
+        )
+        .bind(tenant_id)
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .iter()
+            .map(|row| (row.get("notification_type"), preference_from_row(row)))
+            .collect())
+    }
+
+    async fn set_delivery(&self, tenant_id: Uuid, user_id: Uuid, category: &str, delivery: DeliveryMode) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO public.notification_preferences (tenant_id, user_id, notification_type, delivery, updated_at) \
+             VALUES ($1, $2, $3, $4, NOW()) \
+             ON CONFLICT (tenant_id, user_id, notification_type) \
+             DO UPDATE SET delivery = EXCLUDED.delivery, updated_at = NOW()",
+        )
+        .bind(tenant_id)
+        .bind(user_id)
+        .bind(category)
+        .bind(delivery.as_str())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn email_address(&self, tenant_id: Uuid, user_id: Uuid) -> Result<Option<String>> {
+        user_email(&self.pool, &self.auth_service, tenant_id, user_id)
+            .await
+            .map_err(|e| Error::new(ErrorCode::DatabaseError, e))
+    }
+
+    async fn enqueue(&self, notification: &Notification) -> Result<i64> {
+        let row = sqlx::query(
+            "INSERT INTO public.pending_digest_notifications \
+                 (tenant_id, user_id, category, severity, title, body, location_id, location_name, \
+                  customer_id, customer_name, link, created_at) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12) \
+             RETURNING id",
+        )
+        .bind(notification.tenant_id)
+        .bind(notification.user_id)
+        .bind(&notification.category)
+        .bind(notification.severity.as_str())
+        .bind(&notification.title)
+        .bind(&notification.body)
+        .bind(notification.location_id)
+        .bind(&notification.location_name)
+        .bind(notification.customer_id)
+        .bind(&notification.customer_name)
+        .bind(&notification.link)
+        .bind(notification.created_at)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(row.get("id"))
+    }
+
+    async fn digest_clocks(&self, now: DateTime<Utc>, default_timezone: &str) -> Result<Vec<TenantDigestClock>> {
+        // Unknown timezone names fall back instead of failing the whole query
+        let rows = sqlx::query(
+            "SELECT t.id AS tenant_id, \
+                 ($1::TIMESTAMPTZ AT TIME ZONE COALESCE(tz.name, dtz.name, 'UTC')) AS local_now, \
+                 t.settings ->> $3 AS send_time, \
+                 (SELECT MAX(r.digest_date) FROM public.notification_digest_runs r \
+                  WHERE r.tenant_id = t.id AND r.completed_at IS NOT NULL) AS last_digest_on \
+             FROM public.tenants t \
+             LEFT JOIN pg_timezone_names tz ON tz.name = t.settings ->> $4 \
+             LEFT JOIN pg_timezone_names dtz ON dtz.name = $2 \
+             WHERE t.status = 'active' \
+               AND EXISTS (SELECT 1 FROM public.pending_digest_notifications p \
+                           WHERE p.tenant_id = t.id AND p.sent_at IS NULL)",
+        )
+        .bind(now)
+        .bind(default_timezone)
+        .bind(SEND_TIME_SETTING)
+        .bind(TIMEZONE_SETTING)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| TenantDigestClock {
+                tenant_id: row.get("tenant_id"),
+                local_now: row.get("local_now"),
+                send_time: row.get("send_time"),
+                last_digest_on: row.get("last_digest_on"),
+            })
+            .collect())
+    }
+
+    async fn claim_run(&self, tenant_id: Uuid, date: NaiveDate, now: DateTime<Utc>, stale_before: DateTime<Utc>) -> Result<bool> {
+        let claimed = sqlx::query(
+            "INSERT INTO public.notification_digest_runs (tenant_id, digest_date, started_at) \
+             VALUES ($1, $2, $3) \
+             ON CONFLICT (tenant_id, digest_date) DO UPDATE SET started_at = EXCLUDED.started_at \
+             WHERE notification_digest_runs.completed_at IS NULL AND notification_digest_runs.started_at < $4 \
+             RETURNING tenant_id",
+        )
+        .bind(tenant_id)
+        .bind(date)
+        .bind(now)
+        .bind(stale_before)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(claimed.is_some())
+    }
+
+    async fn complete_run(&self, tenant_id: Uuid, date: NaiveDate, now: DateTime<Utc>) -> Result<()> {
+        sqlx::query(
+            "UPDATE public.notification_digest_runs SET completed_at = $3 \
+             WHERE tenant_id = $1 AND digest_date = $2",
+        )
+        .bind(tenant_id)
+        .bind(date)
+        .bind(now)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn pending(&self, tenant_id: Uuid) -> Result<Vec<PendingNotification>> {
+        let rows = sqlx::query(
+            "SELECT id, tenant_id, user_id, category, severity, title, body, location_id, location_name, \
+                 customer_id, customer_name, link, created_at \
+             FROM public.pending_digest_notifications \
+             WHERE tenant_id = $1 AND sent_at IS NULL \
+             ORDER BY id",
+        )
+        .bind(tenant_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter()
+            .map(|row| {
+                let severity: String = row.get("severity");
+                Ok(PendingNotification {
+                    id: row.get("id"),
+                    notification: Notification {
+                        tenant_id: row.get("tenant_id"),
+                        user_id: row.get("user_id"),
+                        category: row.get("category"),
+                        severity: NotificationSeverity::parse(&severity).ok_or_else(|| {
+                            Error::new(ErrorCode::DatabaseError, format!("Unknown notification severity '{}'", severity))
+                        })?,
+                        title: row.get("title"),
+                        body: row.get("body"),
+                        location_id: row.get("location_id"),
+                        location_name: row.get("location_name"),
+                        customer_id: row.get("customer_id"),
+                        customer_name: row.get("customer_name"),
+                        link: row.get("link"),
+                        created_at: row.get("created_at"),
+                    },
+                })
+            })
+            .collect()
+    }
+
+    async fn mark_sent(&self, ids: &[i64], date: NaiveDate, now: DateTime<Utc>) -> Result<u64> {
+        let result = sqlx::query(
+            "UPDATE public.pending_digest_notifications SET sent_at = $2, digest_date = $3 \
+             WHERE id = ANY($1) AND sent_at IS NULL",
+        )
+        .bind(ids)
+        .bind(now)
+        .bind(date)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+}
+
+/// Delivers notifications and sends the daily digests
+pub struct NotificationService {
+    store: Arc<dyn NotificationStore>,
+    email: Arc<EmailService>,
+    config: NotificationDigestConfig,
+    /// Web app address that notification links are relative to
+    base_url: String,
+    /// Tenant business calendars; without them every day is a working day
+    calendars: Option<Arc<dyn CalendarStore>>,
+}
+
+impl NotificationService {
+    pub fn new(
+        store: Arc<dyn NotificationStore>,
+        email: Arc<EmailService>,
+        config: NotificationDigestConfig,
+        base_url: impl Into<String>,
+    ) -> Self {
+        Self {
+            store,
+            email,
+            config,
+            base_url: base_url.into(),
+            calendars: None,
+        }
+    }
+
+    /// Hold digests on the tenant's non-working days per `notification_digest.calendar_policy`
+    pub fn with_calendar(mut self, calendars: Arc<dyn CalendarStore>) -> Self {
+        self.calendars = Some(calendars);
+        self
+    }
+
+    /// Email the notification now, queue it for the digest, or drop it
+    pub async fn notify(&self, notification: &Notification) -> Result<Delivery> {
+        let preference = self
+            .store
+            .preference(notification.tenant_id, notification.user_id, &notification.category)
+            .await?;
+
+        match delivery_for(notification, &preference, &self.config.digest_categories) {
+            Delivery::Sent => {
+                let Some(address) = self.store.email_address(notification.tenant_id, notification.user_id).await? else {
+                    return Ok(Delivery::Skipped);
+                };
+                let text = match &notification.link {
+                    Some(link) => format!(
+                        "{}\n\n{}",
+                        notification.body.as_deref().unwrap_or_default(),
+                        deep_link(&self.base_url, link)
+                    ),
+                    None => notification.body.clone().unwrap_or_default(),
+                };
+                let html = format!("<pre>{}</pre>", html_escape(&text));
+                self.email
+                    .send_email_with_reference(&address, &notification.title, &html, Some(&text), Some(&reference(notification.tenant_id)))
+                    .await?;
+                Ok(Delivery::Sent)
+            }
+            Delivery::Queued => {
+                self.store.enqueue(notification).await?;
+                Ok(Delivery::Queued)
+            }
+            Delivery::Skipped => Ok(Delivery::Skipped),
+        }
+    }
+
+    pub async fn preferences(&self, tenant_id: Uuid, user_id: Uuid) -> Result<Vec<(String, NotificationPreference)>> {
+        self.store.preferences(tenant_id, user_id).await
+    }
+
+    pub async fn set_delivery(&self, tenant_id: Uuid, user_id: Uuid, category: &str, delivery: DeliveryMode) -> Result<()> {
+        self.store.set_delivery(tenant_id, user_id, category, delivery).await
+    }
+
+    /// Send every tenant digest that is due; returns the number of emails sent
+    pub async fn send_due_digests(&self, now: DateTime<Utc>) -> Result<usize> {
+        let default_send_time = parse_send_time(&self.config.default_send_time)
+            .unwrap_or_else(|| NaiveTime::from_hms_opt(8, 0, 0).expect("valid time"));
+        let stale_before = now - Duration::minutes(STALE_RUN_MINUTES);
+
+        let mut sent = 0;
+        for clock in self.store.digest_clocks(now, &self.config.default_timezone).await? {
+            let send_time = clock
+                .send_time
+                .as_deref()
+                .and_then(parse_send_time)
+                .unwrap_or(default_send_time);
+            let Some(date) = digest_due(clock.local_now, send_time, clock.last_digest_on) else {
+                continue;
+            };
+            // The digest is scheduled daily, so a run shifted off a holiday lands on the next
+            // working day's own run; runs are claimed by that date and fire once
+            if let Some(calendars) = &self.calendars {
+                let calendar = calendar_around(calendars.as_ref(), clock.tenant_id, date).await?;
+                if calendar.due_run(self.config.calendar_policy, date, |_| true).is_none() {
+                    continue;
+                }
+            }
+            if !self.store.claim_run(clock.tenant_id, date, now, stale_before).await? {
+                continue;
+            }
+
+            // A failed run stays claimed and is retried once it goes stale
+            match self.send_tenant_digest(clock.tenant_id, date, now).await {
+                Ok(emails) => {
+                    sent += emails;
+                    self.store.complete_run(clock.tenant_id, date, now).await?;
+                }
+                Err(e) => warn!("Notification digest for tenant {} failed: {}", clock.tenant_id, e),
+            }
+        }
+        Ok(sent)
+    }
+
+    /// One email per user with pending items; a failed email leaves them for the next digest
+    async fn send_tenant_digest(&self, tenant_id: Uuid, date: NaiveDate, now: DateTime<Utc>) -> Result<usize> {
+        let mut by_user: BTreeMap<Uuid, Vec<PendingNotification>> = BTreeMap::new();
+        for item in self.store.pending(tenant_id).await? {
+            by_user.entry(item.notification.user_id).or_default().push(item);
+        }
+
+        let mut sent = 0;
+        for (user_id, items) in by_user {
+            let Some(address) = self.store.email_address(tenant_id, user_id).await? else {
+                continue;
+            };
+
+            let (subject, html, text) = render_digest(&group_digest(&items), date, &self.base_url);
+            if let Err(e) = self
+                .email
+                .send_email_with_reference(&address, &subject, &html, Some(&text), Some(&reference(tenant_id)))
+                .await
+            {
+                warn!("Failed to send notification digest to user {}: {}", user_id, e);
+                continue;
+            }
+
+            let ids: Vec<i64> = items.iter().map(|item| item.id).collect();
+            self.store.mark_sent(&ids, date, now).await?;
+            sent += 1;
+        }
+        Ok(sent)
+    }
+
+    /// Check for due digests every `notification_digest.poll_interval_seconds` in the background
+    pub fn spawn(self: &Arc<Self>) -> Option<tokio::task::JoinHandle<()>> {
+        if !self.config.enabled {
+            return None;
+        }
+
+        let service = Arc::clone(self);
+        Some(tokio::spawn(async move {
+            let mut ticker =
+                tokio::time::interval(std::time::Duration::from_secs(service.config.poll_interval_seconds.max(10)));
+            loop {
+                ticker.tick().await;
+                match service.send_due_digests(Utc::now()).await {
+                    Ok(0) => {}
+                    Ok(sent) => info!("Sent {} notification digest emails", sent),
+                    Err(e) => warn!("Notification digest run failed: {}", e),
+                }
+            }
+        }))
+    }
+}
+
+fn reference(tenant_id: Uuid) -> EmailReference {
+    EmailReference {
+        tenant_id,
+        customer_id: None,
+        sent_by: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::business_calendar::CalendarSettings;
+    use chrono::{Datelike, FixedOffset, TimeZone};
+    use erp_core::calendar::CalendarDay;
+    use erp_auth::email::{SentEmail, SentEmailObserver};
+    use std::collections::{HashMap, HashSet};
+    use std::sync::Mutex;
+
+    /// When a run was claimed and whether it completed
+    type RunState = (DateTime<Utc>, bool);
+
+    #[derive(Default)]
+    struct MemoryStore {
+        preferences: Mutex<HashMap<(Uuid, Uuid, String), NotificationPreference>>,
+        addresses: Mutex<HashMap<Uuid, String>>,
+        /// Each item with the digest date it was sent with
+        items: Mutex<Vec<(PendingNotification, Option<NaiveDate>)>>,
+        runs: Mutex<HashMap<(Uuid, NaiveDate), RunState>>,
+        local_offset: Mutex<Option<FixedOffset>>,
+        /// Address lookups for these users fail, as if the database went away
+        unreachable: Mutex<HashSet<Uuid>>,
+    }
+
+    #[async_trait]
+    impl NotificationStore for MemoryStore {
+        async fn preference(&self, tenant_id: Uuid, user_id: Uuid, category: &str) -> Result<NotificationPreference> {
+            let preferences = self.preferences.lock().unwrap();
+            Ok(preferences.get(&(tenant_id, user_id, category.to_string())).copied().unwrap_or_default())
+        }
+
+        async fn preferences(&self, tenant_id: Uuid, user_id: Uuid) -> Result<Vec<(String, NotificationPreference)>> {
+            let preferences = self.preferences.lock().unwrap();
+            Ok(preferences
+                .iter()
+                .filter(|((t, u, _), _)| *t == tenant_id && *u == user_id)
+                .map(|((_, _, c), p)| (c.clone(), *p))
+                .collect())
+        }
+
+        async fn set_delivery(&self, tenant_id: Uuid, user_id: Uuid, category: &str, delivery: DeliveryMode) -> Result<()> {
+            let mut preferences = self.preferences.lock().unwrap();
+            preferences.entry((tenant_id, user_id, category.to_string())).or_default().delivery = Some(delivery);
+            Ok(())
+        }
+
+        async fn email_address(&self, _tenant_id: Uuid, user_id: Uuid) -> Result<Option<String>> {
+            if self.unreachable.lock().unwrap().contains(&user_id) {
+                return Err(Error::new(ErrorCode::DatabaseConnectionError, "connection reset"));
+            }
+            Ok(self.addresses.lock().unwrap().get(&user_id).cloned())
+        }
+
+        async fn enqueue(&self, notification: &Notification) -> Result<i64> {
+            let mut items = self.items.lock().unwrap();
+            let id = items.len() as i64 + 1;
+            items.push((
+                PendingNotification {
+                    id,
+                    notification: notification.clone(),
+                },
+                None,
+            ));
+            Ok(id)
+        }
+
+        async fn digest_clocks(&self, now: DateTime<Utc>, _default_timezone: &str) -> Result<Vec<TenantDigestClock>> {
+            let offset = self.local_offset.lock().unwrap().unwrap_or(FixedOffset::east_opt(0).unwrap());
+            let items = self.items.lock().unwrap();
+            let runs = self.runs.lock().unwrap();
+            let tenants: HashSet<Uuid> = items
+                .iter()
+                .filter(|(_, sent)| sent.is_none())
+                .map(|(item, _)| item.notification.tenant_id)
+                .collect();
+            Ok(tenants
+                .into_iter()
+                .map(|tenant_id| TenantDigestClock {
+                    tenant_id,
+                    local_now: now.with_timezone(&offset).naive_local(),
+                    send_time: Some("08:00".to_string()),
+                    last_digest_on: runs
+                        .iter()
+                        .filter(|((t, _), (_, completed))| *t == tenant_id && *completed)
+                        .map(|((_, date), _)| *date)
+                        .max(),
+                })
+                .collect())
+        }
+
+        async fn claim_run(&self, tenant_id: Uuid, date: NaiveDate, now: DateTime<Utc>, stale_before: DateTime<Utc>) -> Result<bool> {
+            let mut runs = self.runs.lock().unwrap();
+            match runs.get(&(tenant_id, date)) {
+                Some((started, completed)) if *completed || *started >= stale_before => Ok(false),
+                _ => {
+                    runs.insert((tenant_id, date), (now, false));
+                    Ok(true)
+                }
+            }
+        }
+
+        async fn complete_run(&self, tenant_id: Uuid, date: NaiveDate, _now: DateTime<Utc>) -> Result<()> {
+            if let Some(run) = self.runs.lock().unwrap().get_mut(&(tenant_id, date)) {
+                run.1 = true;
+            }
+            Ok(())
+        }
+
+        async fn pending(&self, tenant_id: Uuid) -> Result<Vec<PendingNotification>> {
+            let items = self.items.lock().unwrap();
+            Ok(items
+                .iter()
+                .filter(|(item, sent)| sent.is_none() && item.notification.tenant_id == tenant_id)
+                .map(|(item, _)| item.clone())
+                .collect())
+        }
+
+        async fn mark_sent(&self, ids: &[i64], date: NaiveDate, _now: DateTime<Utc>) -> Result<u64> {
+            let mut marked = 0;
+            for (item, sent) in self.items.lock().unwrap().iter_mut() {
+                if ids.contains(&item.id) && sent.is_none() {
+                    *sent = Some(date);
+                    marked += 1;
+                }
+            }
+            Ok(marked)
+        }
+    }
+
+    #[derive(Default)]
+    struct Outbox {
+        sent: Mutex<Vec<SentEmail>>,
+    }
+
+    #[async_trait]
+    impl SentEmailObserver for Outbox {
+        async fn email_sent(&self, email: &SentEmail) {
+            self.sent.lock().unwrap().push(email.clone());
+        }
+    }
+
+    impl Outbox {
+        fn to(&self, address: &str) -> Vec<SentEmail> {
+            self.sent.lock().unwrap().iter().filter(|e| e.to == address).cloned().collect()
+        }
+    }
+
+    fn service(store: Arc<MemoryStore>, outbox: Arc<Outbox>) -> NotificationService {
+        let config = NotificationDigestConfig {
+            digest_categories: vec!["inventory_alert".to_string()],
+            ..NotificationDigestConfig::default()
+        };
+        let email = Arc::new(EmailService::mock().with_observer(outbox));
+        NotificationService::new(store, email, config, "https://erp.example.com")
+    }
+
+    fn notification(tenant_id: Uuid, user_id: Uuid, category: &str, title: &str) -> Notification {
+        Notification {
+            tenant_id,
+            user_id,
+            category: category.to_string(),
+            severity: NotificationSeverity::Info,
+            title: title.to_string(),
+            body: None,
+            location_id: None,
+            location_name: None,
+            customer_id: None,
+            customer_name: None,
+            link: None,
+            created_at: Utc::now(),
+        }
+    }
+
+    fn at_location(mut n: Notification, name: &str) -> Notification {
+        n.location_id = Some(Uuid::from_u128(name.len() as u128));
+        n.location_name = Some(name.to_string());
+        n
+    }
+
+    fn pending(id: i64, notification: Notification) -> PendingNotification {
+        PendingNotification { id, notification }
+    }
+
+    /// One German Monday-to-Friday calendar for every tenant
+    struct GermanCalendar;
+
+    #[async_trait]
+    impl CalendarStore for GermanCalendar {
+        async fn settings(&self, _tenant_id: Uuid) -> Result<Option<CalendarSettings>> {
+            Ok(Some(CalendarSettings::default()))
+        }
+
+        async fn save_settings(&self, _tenant_id: Uuid, _settings: &CalendarSettings) -> Result<()> {
+            Ok(())
+        }
+
+        async fn days(&self, _tenant_id: Uuid, from: NaiveDate, to: NaiveDate) -> Result<Vec<CalendarDay>> {
+            let days = erp_core::calendar::holidays::calendar_days("DE", from.year())?;
+            Ok(days.into_iter().filter(|day| day.date >= from && day.date <= to).collect())
+        }
+
+        async fn save_day(&self, _tenant_id: Uuid, _day: &CalendarDay) -> Result<()> {
+            Ok(())
+        }
+
+        async fn delete_day(&self, _tenant_id: Uuid, _date: NaiveDate) -> Result<bool> {
+            Ok(false)
+        }
+
+        async fn import_days(&self, _tenant_id: Uuid, _country: &str, _days: &[CalendarDay]) -> Result<u64> {
+            Ok(0)
+        }
+    }
+
+    fn utc(y: i32, m: u32, d: u32, h: u32, min: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(y, m, d, h, min, 0).unwrap()
+    }
+
+    #[test]
+    fn test_grouping_and_rendering() {
+        let (tenant, user) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut overdue = notification(tenant, user, "payment_overdue", "Invoice 1001 overdue");
+        overdue.customer_id = Some(Uuid::new_v4());
+        overdue.customer_name = Some("Acme & Sons".to_string());
+        overdue.link = Some("/customers/acme".to_string());
+        let mut low = at_location(notification(tenant, user, "inventory_alert", "Low stock: SKU-2"), "Warehouse North");
+        low.severity = NotificationSeverity::Warning;
+        low.link = Some("/inventory/alerts/2".to_string());
+
+        let items = vec![
+            pending(1, at_location(notification(tenant, user, "inventory_alert", "Low stock: SKU-1"), "Warehouse North")),
+            pending(2, notification(tenant, user, "inventory_alert", "Reorder suggestions ready")),
+            pending(3, overdue),
+            pending(4, at_location(notification(tenant, user, "inventory_alert", "Low stock: SKU-9"), "Dock <South>")),
+            pending(5, low),
+        ];
+
+        let sections = group_digest(&items);
+        assert_eq!(sections.len(), 2);
+        assert_eq!((sections[0].category.as_str(), sections[0].count), ("inventory_alert", 4));
+        let labels: Vec<_> = sections[0].groups.iter().map(|g| g.label.as_str()).collect();
+        assert_eq!(labels, ["Location Dock <South>", "Location Warehouse North", "Other"]);
+        let north: Vec<i64> = sections[0].groups[1].items.iter().map(|i| i.id).collect();
+        assert_eq!(north, [1, 5]);
+        assert_eq!(sections[1].groups[0].label, "Customer Acme & Sons");
+
+        let date = NaiveDate::from_ymd_opt(2026, 3, 2).unwrap();
+        let (subject, html, text) = render_digest(&sections, date, "https://erp.example.com/");
+        assert_eq!(subject, "Daily summary for 2026-03-02: 5 notification(s)");
+        assert!(text.contains("Inventory alert (4)\n  Location Dock <South> (1)\n"));
+        assert!(text.contains("  - Low stock: SKU-2 [warning] https://erp.example.com/inventory/alerts/2\n"));
+        assert!(text.contains("Payment overdue (1)\n  Customer Acme & Sons (1)\n"));
+        assert!(html.contains("<h4>Location Dock &lt;South&gt; (1)</h4>"));
+        assert!(html.contains("<li><a href=\"https://erp.example.com/customers/acme\">Invoice 1001 overdue</a></li>"));
+        assert!(html.contains("<h4>Customer Acme &amp; Sons (1)</h4>"));
+    }
+
+    #[test]
+    fn test_send_window_follows_local_time() {
+        let eight = parse_send_time("08:00").unwrap();
+        let berlin_summer = FixedOffset::east_opt(2 * 3600).unwrap();
+        let berlin_winter = FixedOffset::east_opt(3600).unwrap();
+        let local = |at: DateTime<Utc>, offset: FixedOffset| at.with_timezone(&offset).naive_local();
+
+        // 06:30 UTC is 08:30 in Berlin in summer, 07:30 in winter
+        let summer = utc(2026, 7, 1, 6, 30);
+        let winter = utc(2026, 1, 15, 6, 30);
+        assert_eq!(digest_due(local(summer, berlin_summer), eight, None), Some(summer.date_naive()));
+        assert_eq!(digest_due(local(winter, berlin_winter), eight, None), None);
+        assert!(digest_due(local(winter + Duration::hours(1), berlin_winter), eight, None).is_some());
+
+        // Once per local day: 23:30 UTC is already the next day in Tokyo
+        let tokyo = FixedOffset::east_opt(9 * 3600).unwrap();
+        let late = utc(2026, 7, 1, 23, 30);
+        let tokyo_date = NaiveDate::from_ymd_opt(2026, 7, 2).unwrap();
+        assert_eq!(digest_due(local(late, tokyo), eight, None), Some(tokyo_date));
+        assert_eq!(digest_due(local(late, tokyo), eight, Some(tokyo_date)), None);
+        assert_eq!(
+            digest_due(local(late, tokyo), eight, Some(tokyo_date.pred_opt().unwrap())),
+            Some(tokyo_date)
+        );
+
+        assert_eq!(parse_send_time(" 17:45 "), NaiveTime::from_hms_opt(17, 45, 0));
+        assert!(parse_send_time("8am").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_critical_bypasses_digest() {
+        let (store, outbox) = (Arc::new(MemoryStore::default()), Arc::new(Outbox::default()));
+        let service = service(store.clone(), outbox.clone());
+        let (tenant, user) = (Uuid::new_v4(), Uuid::new_v4());
+        store.addresses.lock().unwrap().insert(user, "ops@example.com".to_string());
+
+        let low = notification(tenant, user, "inventory_alert", "Low stock: SKU-1");
+        assert_eq!(service.notify(&low).await.unwrap(), Delivery::Queued);
+
+        let mut stockout = notification(tenant, user, "inventory_alert", "Stockout: SKU-1");
+        stockout.severity = NotificationSeverity::Critical;
+        assert_eq!(service.notify(&stockout).await.unwrap(), Delivery::Sent);
+
+        // Other categories go out right away unless the user collects them
+        let order = notification(tenant, user, "order_update", "Order 7 shipped");
+        assert_eq!(service.notify(&order).await.unwrap(), Delivery::Sent);
+        service.set_delivery(tenant, user, "order_update", DeliveryMode::Digest).await.unwrap();
+        assert_eq!(service.notify(&order).await.unwrap(), Delivery::Queued);
+
+        store.preferences.lock().unwrap().insert(
+            (tenant, user, "inventory_alert".to_string()),
+            NotificationPreference { email_enabled: false, delivery: None },
+        );
+        assert_eq!(service.notify(&low).await.unwrap(), Delivery::Skipped);
+        assert_eq!(service.notify(&stockout).await.unwrap(), Delivery::Sent);
+
+        let subjects: Vec<String> = outbox.to("ops@example.com").into_iter().map(|e| e.subject).collect();
+        assert_eq!(subjects, ["Stockout: SKU-1", "Order 7 shipped", "Stockout: SKU-1"]);
+        assert_eq!(store.items.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_digest_waits_for_next_working_day() {
+        let (store, outbox) = (Arc::new(MemoryStore::default()), Arc::new(Outbox::default()));
+        let service = service(store.clone(), outbox.clone()).with_calendar(Arc::new(GermanCalendar));
+        let (tenant, user) = (Uuid::new_v4(), Uuid::new_v4());
+        store.addresses.lock().unwrap().insert(user, "ops@example.com".to_string());
+        service.notify(&notification(tenant, user, "inventory_alert", "Low stock: SKU-1")).await.unwrap();
+
+        // Good Friday, the Easter weekend and Easter Monday 2026 are not working days
+        for day in 3..=6 {
+            assert_eq!(service.send_due_digests(utc(2026, 4, day, 9, 0)).await.unwrap(), 0);
+        }
+        assert!(outbox.to("ops@example.com").is_empty());
+
+        // Tuesday's digest carries the held items and goes out once
+        assert_eq!(service.send_due_digests(utc(2026, 4, 7, 9, 0)).await.unwrap(), 1);
+        assert_eq!(service.send_due_digests(utc(2026, 4, 7, 15, 0)).await.unwrap(), 0);
+        assert_eq!(outbox.to("ops@example.com").len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_crashed_run_resumes_without_duplicates() {
+        let (store, outbox) = (Arc::new(MemoryStore::default()), Arc::new(Outbox::default()));
+        let service = service(store.clone(), outbox.clone());
+        let tenant = Uuid::new_v4();
+        // Users are emailed in id order
+        let (alice, bob, carol, dave) = (Uuid::from_u128(1), Uuid::from_u128(2), Uuid::from_u128(3), Uuid::from_u128(4));
+        for (user, address) in [(alice, "alice@example.com"), (bob, "bob@example.com"), (carol, "fail@example.com")] {
+            store.addresses.lock().unwrap().insert(user, address.to_string());
+        }
+        for (user, title) in [(alice, "A1"), (alice, "A2"), (bob, "B1"), (carol, "C1")] {
+            service.notify(&notification(tenant, user, "inventory_alert", title)).await.unwrap();
+        }
+        // dave has nothing pending and gets nothing
+        store.addresses.lock().unwrap().insert(dave, "dave@example.com".to_string());
+
+        // Before the send time nothing goes out
+        let morning = utc(2026, 3, 2, 7, 0);
+        assert_eq!(service.send_due_digests(morning).await.unwrap(), 0);
+
+        // The run dies after alice's digest went out
+        store.unreachable.lock().unwrap().insert(bob);
+        let run = utc(2026, 3, 2, 8, 0);
+        assert_eq!(service.send_due_digests(run).await.unwrap(), 0);
+        assert_eq!(outbox.to("alice@example.com").len(), 1);
+
+        // The dead run still holds the day until it goes stale
+        store.unreachable.lock().unwrap().clear();
+        assert_eq!(service.send_due_digests(run + Duration::minutes(5)).await.unwrap(), 0);
+        assert!(outbox.to("bob@example.com").is_empty());
+
+        let resumed = run + Duration::minutes(STALE_RUN_MINUTES + 1);
+        assert_eq!(service.send_due_digests(resumed).await.unwrap(), 1);
+        assert_eq!(outbox.to("alice@example.com").len(), 1, "alice is not sent twice");
+        assert_eq!(outbox.to("bob@example.com").len(), 1);
+        assert!(outbox.to("dave@example.com").is_empty());
+        assert!(outbox.to("alice@example.com")[0].text_body.as_deref().unwrap().contains("- A2"));
+
+        // carol's email bounced: her item waits for tomorrow, today's digest is done
+        assert_eq!(service.send_due_digests(resumed + Duration::hours(1)).await.unwrap(), 0);
+        let waiting: Vec<String> = store.pending(tenant).await.unwrap().into_iter().map(|p| p.notification.title).collect();
+        assert_eq!(waiting, ["C1"]);
+
+        store.addresses.lock().unwrap().insert(carol, "carol@example.com".to_string());
+        service.notify(&notification(tenant, alice, "inventory_alert", "A3")).await.unwrap();
+        assert_eq!(service.send_due_digests(utc(2026, 3, 3, 8, 0)).await.unwrap(), 2);
+        assert_eq!(outbox.to("carol@example.com").len(), 1);
+        let second = &outbox.to("alice@example.com")[1];
+        assert!(second.subject.contains("1 notification(s)"));
+        assert!(store.pending(tenant).await.unwrap().is_empty());
+    }
+}
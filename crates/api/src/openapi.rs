@@ -0,0 +1,602 @@
+//! OpenAPI document of the API and JSON Schemas of its DTOs
+//!
+//! The document is built without a running server, so the same [`spec`] is
+//! served on `/api-docs/openapi.json` and written as static artifacts by the
+//! `generate-openapi` binary: `openapi.json` plus one standalone JSON Schema
+//! per component under `schemas/`, committed in [`ARTIFACTS_DIR`]. The webhook
+//! envelope of every event type gets its JSON Schema under `webhooks/`, named
+//! `{source}.{type}.json`. CI runs the binary with `--check`, which fails when
+//! an annotation or an event type changed without the artifacts being
+//! regenerated.
+//!
+//! Every operation that needs a token documents the expiry headers the auth
+//! middleware adds to its responses, see [`TOKEN_EXPIRY_GUIDANCE`].
+
+use crate::build_info::build_info;
+use crate::handlers::{customers, roles, users};
+use crate::health;
+use crate::webhooks::{envelope_schema, example_envelope, EVENT_TYPES};
+use axum::{
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use erp_master_data::{CreateCustomerRequest, CreateProductRequest};
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use utoipa::openapi::extensions::ExtensionsBuilder;
+use utoipa::openapi::header::{Header, HeaderBuilder};
+use utoipa::openapi::security::SecurityRequirement;
+use utoipa::openapi::{Object, RefOr, Type};
+use utoipa::OpenApi;
+
+/// Where the artifacts are committed, relative to the workspace root
+pub const ARTIFACTS_DIR: &str = "docs/api";
+
+/// `info` extension with the commit the document was built from
+pub const GIT_COMMIT_EXTENSION: &str = "x-git-commit";
+
+const JSON_SCHEMA_DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+const COMPONENT_REF_PREFIX: &str = "#/components/schemas/";
+
+/// Directories of generated schema files, relative to the artifacts directory
+const SCHEMA_DIRS: [&str; 2] = ["schemas", "webhooks"];
+
+/// Description of the document: how clients should handle access token expiry
+pub const TOKEN_EXPIRY_GUIDANCE: &str = "\
+## Access token expiry
+
+Every authenticated response carries `X-Token-Expires-In`, the seconds the access token \
+is still accepted. Once less than a fifth of its lifetime is left (by default), the response also carries \
+`X-Token-Refresh-Advised: true`. Clients should then get a new token pair from \
+`POST /api/v1/auth/refresh-token` in the background rather than wait for a 401.
+
+## Sliding sessions
+
+Tenants can turn on sliding sessions (`sliding_sessions` in `PUT /api/v1/settings/sessions`). \
+Their users get a new access token in `X-Refreshed-Access-Token` on the first response that \
+advises a refresh, so active users are never signed out. The header is only sent over HTTPS \
+and never for impersonation sessions, which have to be started anew. Clients should use the \
+new token for every request from then on. The token it replaces stays valid for a short grace \
+period, 30 seconds by default, so requests already sent with it still succeed.";
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "ERP System API"),
+    paths(
+        health::health_check,
+        health::readiness_check,
+        customers::list_customers,
+        customers::create_customer,
+        users::list_users,
+        roles::list_roles,
+    ),
+    components(schemas(
+        CreateCustomerRequest,
+        CreateProductRequest,
+    )),
+    tags(
+        (name = "health", description = "Health check endpoints"),
+        (name = "auth", description = "Authentication and authorization"),
+        (name = "users", description = "User management"),
+        (name = "roles", description = "Role and permission management"),
+        (name = "customers", description = "Customer master data"),
+        (name = "products", description = "Product master data"),
+    )
+)]
+pub struct ApiDoc;
+
+/// The full document: the API's own paths and schemas, the auth DTOs, and the
+/// version and git commit of this build in `info`
+pub fn spec() -> utoipa::openapi::OpenApi {
+    let mut doc = ApiDoc::openapi();
+    // Our tag descriptions win over the auth crate's ones of the same name
+    let mut auth = erp_auth::AuthApiDoc::openapi();
+    if let (Some(tags), Some(auth_tags)) = (&doc.tags, &mut auth.tags) {
+        auth_tags.retain(|tag| !tags.iter().any(|t| t.name == tag.name));
+    }
+    doc.merge(auth);
+
+    document_token_expiry_headers(&mut doc);
+
+    let build = build_info();
+    doc.info.description = Some(TOKEN_EXPIRY_GUIDANCE.to_string());
+    doc.info.version = build.version.to_string();
+    doc.info.extensions = Some(ExtensionsBuilder::new().add(GIT_COMMIT_EXTENSION, build.commit.as_str()).build());
+    doc
+}
+
+/// Add the auth middleware's expiry headers to every response of the
+/// operations that need a token; public ones opt out with `security(())`
+fn document_token_expiry_headers(doc: &mut utoipa::openapi::OpenApi) {
+    let headers = [
+        ("X-Token-Expires-In", Type::Integer, "Seconds until the access token expires"),
+        ("X-Token-Refresh-Advised", Type::Boolean, "`true` once the access token should be refreshed"),
+        (
+            "X-Refreshed-Access-Token",
+            Type::String,
+            "New access token for tenants with sliding sessions; use it from now on",
+        ),
+    ]
+    .map(|(name, schema_type, description)| {
+        let header: Header = HeaderBuilder::new()
+            .schema(Object::with_type(schema_type))
+            .description(Some(description))
+            .build();
+        (name.to_string(), header)
+    });
+
+    for item in doc.paths.paths.values_mut() {
+        let operations = [
+            &mut item.get,
+            &mut item.put,
+            &mut item.post,
+            &mut item.delete,
+            &mut item.patch,
+        ];
+        for operation in operations.into_iter().flatten() {
+            let public = operation
+                .security
+                .as_ref()
+                .is_some_and(|requirements| requirements.iter().all(|r| *r == SecurityRequirement::default()));
+            if public {
+                continue;
+            }
+            for response in operation.responses.responses.values_mut() {
+                if let RefOr::T(response) = response {
+                    response.headers.extend(headers.clone());
+                }
+            }
+        }
+    }
+}
+
+/// One standalone JSON Schema per component schema of `doc`, keyed by name
+///
+/// Each carries the components it references under `$defs`, so it validates
+/// a payload without the rest of the document.
+pub fn dto_schemas(doc: &utoipa::openapi::OpenApi) -> BTreeMap<String, Value> {
+    let components: Map<String, Value> = doc
+        .components
+        .as_ref()
+        .map(|c| serde_json::to_value(&c.schemas).expect("schemas serialize"))
+        .and_then(|v| match v {
+            Value::Object(map) => Some(map),
+            _ => None,
+        })
+        .unwrap_or_default();
+
+    components
+        .iter()
+        .map(|(name, schema)| {
+            let mut defs = Map::new();
+            let mut pending: Vec<String> = component_refs(schema).into_iter().collect();
+            while let Some(referenced) = pending.pop() {
+                if &referenced == name || defs.contains_key(&referenced) {
+                    continue;
+                }
+                if let Some(def) = components.get(&referenced) {
+                    pending.extend(component_refs(def));
+                    defs.insert(referenced, def.clone());
+                }
+            }
+
+            let mut standalone = Map::new();
+            standalone.insert("$schema".into(), json!(JSON_SCHEMA_DIALECT));
+            standalone.insert("title".into(), json!(name));
+            if let Value::Object(fields) = schema {
+                standalone.extend(fields.clone());
+            }
+            if !defs.is_empty() {
+                standalone.insert("$defs".into(), Value::Object(defs));
+            }
+            let mut standalone = Value::Object(standalone);
+            rewrite_refs(&mut standalone, name);
+            (name.clone(), standalone)
+        })
+        .collect()
+}
+
+/// Names of the components `schema` references
+fn component_refs(schema: &Value) -> BTreeSet<String> {
+    let mut refs = BTreeSet::new();
+    visit_refs(schema, &mut |r| {
+        if let Some(name) = r.strip_prefix(COMPONENT_REF_PREFIX) {
+            refs.insert(name.to_string());
+        }
+    });
+    refs
+}
+
+fn visit_refs(value: &Value, f: &mut impl FnMut(&str)) {
+    match value {
+        Value::Object(map) => {
+            for (key, v) in map {
+                match (key.as_str(), v) {
+                    ("$ref", Value::String(r)) => f(r),
+                    _ => visit_refs(v, f),
+                }
+            }
+        }
+        Value::Array(items) => items.iter().for_each(|v| visit_refs(v, f)),
+        _ => {}
+    }
+}
+
+/// Point component references at `$defs`, and those to `root` at the document itself
+fn rewrite_refs(value: &mut Value, root: &str) {
+    match value {
+        Value::Object(map) => {
+            for (key, v) in map.iter_mut() {
+                match (key.as_str(), v) {
+                    ("$ref", Value::String(r)) => {
+                        if let Some(name) = r.strip_prefix(COMPONENT_REF_PREFIX) {
+                            *r = if name == root { "#".to_string() } else { format!("#/$defs/{}", name) };
+                        }
+                    }
+                    (_, v) => rewrite_refs(v, root),
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(|v| rewrite_refs(v, root)),
+        _ => {}
+    }
+}
+
+/// Standalone JSON Schema of each webhook event type's envelope, by `{source}.{type}`
+pub fn webhook_schemas() -> BTreeMap<String, Value> {
+    EVENT_TYPES
+        .iter()
+        .map(|doc| {
+            let mut standalone = Map::new();
+            standalone.insert("$schema".into(), json!(JSON_SCHEMA_DIALECT));
+            standalone.insert("title".into(), json!(doc.qualified_name()));
+            if let Value::Object(fields) = envelope_schema(doc) {
+                standalone.extend(fields);
+            }
+            standalone.insert("examples".into(), json!([example_envelope(doc)]));
+            (doc.qualified_name(), Value::Object(standalone))
+        })
+        .collect()
+}
+
+/// The artifact files of `doc`: paths relative to the artifacts directory and
+/// their pretty-printed contents
+pub fn artifacts(doc: &utoipa::openapi::OpenApi) -> Vec<(PathBuf, String)> {
+    let mut files = vec![(PathBuf::from("openapi.json"), pretty(&serde_json::to_value(doc).expect("spec serializes")))];
+    files.extend(
+        dto_schemas(doc)
+            .into_iter()
+            .map(|(name, schema)| (Path::new("schemas").join(format!("{}.json", name)), pretty(&schema))),
+    );
+    files.extend(
+        webhook_schemas()
+            .into_iter()
+            .map(|(name, schema)| (Path::new("webhooks").join(format!("{}.json", name)), pretty(&schema))),
+    );
+    files
+}
+
+fn pretty(value: &Value) -> String {
+    let mut out = serde_json::to_string_pretty(value).expect("JSON serializes");
+    out.push('\n');
+    out
+}
+
+/// Write the artifacts into `dir`, dropping schema files of removed components
+/// and event types
+pub fn write_artifacts(dir: &Path, files: &[(PathBuf, String)]) -> std::io::Result<()> {
+    for schemas in SCHEMA_DIRS {
+        std::fs::create_dir_all(dir.join(schemas))?;
+    }
+    for stale in stale_schema_files(dir, files)? {
+        std::fs::remove_file(dir.join(stale))?;
+    }
+    for (path, contents) in files {
+        std::fs::write(dir.join(path), contents)?;
+    }
+    Ok(())
+}
+
+/// How a committed artifact differs from the generated one
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ArtifactDrift {
+    /// Generated but not committed
+    Missing(PathBuf),
+    /// Committed with other contents than generated
+    Changed(PathBuf),
+    /// Committed schema of a component or event type that no longer exists
+    Stale(PathBuf),
+}
+
+impl std::fmt::Display for ArtifactDrift {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ArtifactDrift::Missing(path) => write!(f, "missing: {}", path.display()),
+            ArtifactDrift::Changed(path) => write!(f, "out of date: {}", path.display()),
+            ArtifactDrift::Stale(path) => write!(f, "no longer generated: {}", path.display()),
+        }
+    }
+}
+
+/// Compare the artifacts in `dir` with the generated `files`
+///
+/// Files are compared as JSON, ignoring the git commit in `info`: the commit
+/// that updates the artifacts can't be the one they name.
+pub fn check_artifacts(dir: &Path, files: &[(PathBuf, String)]) -> std::io::Result<Vec<ArtifactDrift>> {
+    let mut drift = Vec::new();
+    for (path, contents) in files {
+        let committed = match std::fs::read_to_string(dir.join(path)) {
+            Ok(committed) => committed,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                drift.push(ArtifactDrift::Missing(path.clone()));
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        if comparable(&committed) != comparable(contents) {
+            drift.push(ArtifactDrift::Changed(path.clone()));
+        }
+    }
+    drift.extend(stale_schema_files(dir, files)?.into_iter().map(ArtifactDrift::Stale));
+    Ok(drift)
+}
+
+fn comparable(contents: &str) -> Option<Value> {
+    let mut value: Value = serde_json::from_str(contents).ok()?;
+    if let Some(info) = value.get_mut("info").and_then(Value::as_object_mut) {
+        info.remove(GIT_COMMIT_EXTENSION);
+    }
+    Some(value)
+}
+
+fn stale_schema_files(dir: &Path, files: &[(PathBuf, String)]) -> std::io::Result<Vec<PathBuf>> {
+    let mut stale = Vec::new();
+    for schemas in SCHEMA_DIRS {
+        if !dir.join(schemas).is_dir() {
+            continue;
+        }
+        for entry in std::fs::read_dir(dir.join(schemas))? {
+            let path = Path::new(schemas).join(entry?.file_name());
+            if path.extension().is_some_and(|ext| ext == "json") && !files.iter().any(|(p, _)| p == &path) {
+                stale.push(path);
+            }
+        }
+    }
+    stale.sort();
+    Ok(stale)
+}
+
+/// ETag of the served document: the build commit, weak for a build of a dirty
+/// checkout since that commit doesn't pin its contents
+fn etag() -> String {
+    let build = build_info();
+    if build.dirty {
+        format!("W/\"{}-{}\"", build.commit, build.built_at)
+    } else {
+        format!("\"{}\"", build.commit)
+    }
+}
+
+fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+    let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    if_none_match.trim() == "*" || if_none_match.split(',').any(|tag| opaque(tag) == opaque(etag))
+}
+
+/// `GET /api-docs/openapi.json`; clients revalidate with `If-None-Match`
+pub async fn openapi_json(headers: HeaderMap) -> Response {
+    static DOCUMENT: OnceLock<String> = OnceLock::new();
+
+    let etag = etag();
+    let not_modified = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| etag_matches(v, &etag));
+    let caching = [
+        (header::ETAG, HeaderValue::from_str(&etag).expect("commit is a valid header value")),
+        (header::CACHE_CONTROL, HeaderValue::from_static("no-cache")),
+    ];
+    if not_modified {
+        return (StatusCode::NOT_MODIFIED, caching).into_response();
+    }
+
+    let document = DOCUMENT.get_or_init(|| spec().to_json().expect("spec serializes"));
+    (
+        caching,
+        [(header::CONTENT_TYPE, HeaderValue::from_static("application/json"))],
+        document.clone(),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schema_validator(name: &str) -> jsonschema::JSONSchema {
+        let schemas = dto_schemas(&spec());
+        jsonschema::JSONSchema::options()
+            .with_draft(jsonschema::Draft::Draft202012)
+            .compile(&schemas[name])
+            .expect("schema compiles")
+    }
+
+    #[test]
+    fn test_spec_builds_headlessly_with_version_and_commit() {
+        let doc: Value = serde_json::from_str(&spec().to_json().unwrap()).unwrap();
+
+        assert!(doc["paths"]["/health"]["get"].is_object());
+        assert_eq!(doc["info"]["version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(doc["info"][GIT_COMMIT_EXTENSION], build_info().commit);
+        // DTOs of the master data and the auth crates
+        assert!(doc["components"]["schemas"]["CreateCustomerRequest"].is_object());
+        assert!(doc["components"]["schemas"]["LoginRequest"].is_object());
+    }
+
+    #[test]
+    fn test_authenticated_operations_document_token_expiry_headers() {
+        let doc: Value = serde_json::from_str(&spec().to_json().unwrap()).unwrap();
+
+        for status in ["201", "400", "403", "409"] {
+            let headers = &doc["paths"]["/api/v1/customers"]["post"]["responses"][status]["headers"];
+            assert_eq!(headers["X-Token-Expires-In"]["schema"]["type"], "integer");
+            assert_eq!(headers["X-Token-Refresh-Advised"]["schema"]["type"], "boolean");
+            assert_eq!(headers["X-Refreshed-Access-Token"]["schema"]["type"], "string");
+        }
+        // Alongside the operation's own headers
+        assert!(doc["paths"]["/api/v1/customers"]["post"]["responses"]["201"]["headers"]["Location"].is_object());
+
+        // Public operations get none
+        assert!(doc["paths"]["/health"]["get"]["responses"]["200"]["headers"].is_null());
+        assert!(doc["info"]["description"].as_str().unwrap().contains("X-Refreshed-Access-Token"));
+    }
+
+    #[test]
+    fn test_check_detects_drifted_annotation() {
+        let dir = std::env::temp_dir().join(format!("erp-openapi-{}", uuid::Uuid::new_v4()));
+        let files = artifacts(&spec());
+        write_artifacts(&dir, &files).unwrap();
+        assert!(check_artifacts(&dir, &files).unwrap().is_empty());
+
+        // A build from another commit is not drift
+        let mut rebuilt = spec();
+        rebuilt.info.extensions = Some(ExtensionsBuilder::new().add(GIT_COMMIT_EXTENSION, "0000000").build());
+        assert!(check_artifacts(&dir, &artifacts(&rebuilt)).unwrap().is_empty());
+
+        // An edited operation summary is
+        let mut drifted = spec();
+        let health = drifted.paths.paths.get_mut("/health").unwrap();
+        health.get.as_mut().unwrap().summary = Some("Edited summary".to_string());
+        let drift = check_artifacts(&dir, &artifacts(&drifted)).unwrap();
+        assert_eq!(drift, vec![ArtifactDrift::Changed(PathBuf::from("openapi.json"))]);
+
+        // So is a schema file of a removed component
+        std::fs::write(dir.join("schemas/Removed.json"), "{}").unwrap();
+        std::fs::remove_file(dir.join("schemas/CreateProductRequest.json")).unwrap();
+        let drift = check_artifacts(&dir, &files).unwrap();
+        assert_eq!(
+            drift,
+            vec![
+                ArtifactDrift::Missing(PathBuf::from("schemas/CreateProductRequest.json")),
+                ArtifactDrift::Stale(PathBuf::from("schemas/Removed.json")),
+            ]
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_create_customer_schema_validates_payloads() {
+        let validator = schema_validator("CreateCustomerRequest");
+
+        let payload = json!({
+            "legal_name": "Acme GmbH",
+            "customer_type": "B2b",
+            "lifecycle_stage": "ActiveCustomer",
+            "addresses": [{
+                "address_type": "Billing",
+                "street_line_1": "Hauptstraße 1",
+                "city": "Berlin",
+                "postal_code": "10115",
+                "country_code": "DE",
+                "is_primary": true
+            }],
+            "financial_info": {
+                "currency_code": "EUR",
+                "credit_limit": "5000.00",
+                "payment_terms": { "payment_method": "BankTransfer", "net_days": 30 }
+            },
+            "tax_numbers": { "vat": "DE123456789" }
+        });
+        assert!(validator.is_valid(&payload));
+        assert!(serde_json::from_value::<CreateCustomerRequest>(payload).is_ok());
+
+        assert!(!validator.is_valid(&json!({ "customer_type": "B2b" })));
+        assert!(!validator.is_valid(&json!({ "legal_name": "Acme GmbH", "customer_type": "Wholesale" })));
+    }
+
+    #[test]
+    fn test_create_product_schema_validates_payloads() {
+        let validator = schema_validator("CreateProductRequest");
+
+        let payload = json!({
+            "sku": "WID-001",
+            "name": "Widget",
+            "product_type": "Physical",
+            "unit_of_measure": "Piece",
+            "base_price": 1999,
+            "currency": "EUR",
+            "is_tracked": true,
+            "tags": ["hardware"]
+        });
+        assert!(validator.is_valid(&payload));
+        assert!(serde_json::from_value::<CreateProductRequest>(payload.clone()).is_ok());
+
+        let mut wrong_price = payload;
+        wrong_price["base_price"] = json!("19.99");
+        assert!(!validator.is_valid(&wrong_price));
+    }
+
+    #[test]
+    fn test_webhook_schema_is_generated_for_each_event_type() {
+        let files = artifacts(&spec());
+        for doc in EVENT_TYPES {
+            let path = PathBuf::from(format!("webhooks/{}.json", doc.qualified_name()));
+            let (_, contents) = files.iter().find(|(p, _)| p == &path).unwrap_or_else(|| panic!("no {}", path.display()));
+            let schema: Value = serde_json::from_str(contents).unwrap();
+            let validator = jsonschema::JSONSchema::options()
+                .with_draft(jsonschema::Draft::Draft202012)
+                .compile(&schema)
+                .expect("schema compiles");
+
+            let example = example_envelope(doc);
+            assert!(validator.is_valid(&example), "example of {} is invalid", doc.qualified_name());
+            assert_eq!(schema["examples"][0], example);
+
+            // The envelope pins its source and type, and data is checked too
+            let mut other_type = example.clone();
+            other_type["type"] = json!("something_else");
+            assert!(!validator.is_valid(&other_type));
+            let mut no_data_fields = example;
+            no_data_fields["data"] = json!({});
+            assert!(!validator.is_valid(&no_data_fields));
+        }
+        let webhook_files = files.iter().filter(|(p, _)| p.starts_with("webhooks")).count();
+        assert_eq!(webhook_files, EVENT_TYPES.len());
+    }
+
+    #[test]
+    fn test_committed_artifacts_are_current() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../..").join(ARTIFACTS_DIR);
+        let drift = check_artifacts(&dir, &artifacts(&spec())).unwrap();
+        assert!(
+            drift.is_empty(),
+            "OpenAPI artifacts are out of date, run `cargo run -p erp-api --bin generate-openapi`: {:?}",
+            drift
+        );
+    }
+
+    #[tokio::test]
+    async fn test_served_document_revalidates_on_build_etag() {
+        let response = openapi_json(HeaderMap::new()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let served_etag = response.headers()[header::ETAG].clone();
+        assert_eq!(served_etag.to_str().unwrap(), etag());
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::IF_NONE_MATCH, served_etag);
+        let response = openapi_json(headers).await;
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()[header::ETAG].to_str().unwrap(), etag());
+    }
+
+    #[test]
+    fn test_etag_matching() {
+        assert!(etag_matches("\"abc\"", "\"abc\""));
+        assert!(etag_matches("W/\"abc\", \"def\"", "\"def\""));
+        assert!(etag_matches("\"abc\"", "W/\"abc\""));
+        assert!(etag_matches("*", "\"abc\""));
+        assert!(!etag_matches("\"abc\"", "\"abd\""));
+    }
+}
@@ -0,0 +1,399 @@
+//! # Permission Usage Tracking
+//!
+//! Drains the [`PermissionUsageRecorder`] channel into per-day Redis counters
+//! every `permission_usage.write_interval_ms`, and once a night at
+//! `permission_usage.flush_at_hour_utc` moves finished days into
+//! `public.permission_usage_daily`. Reports for the admin endpoints under
+//! `/api/v1/admin/permissions/usage` combine those rows with the tenant's roles.
+//!
+//! Redis keeps one hash per day, `permission_usage:{date}`, with a field per
+//! `{tenant}|{user}|{permission}`, suffixed `|override` for uses under
+//! impersonation. A day is flushed by renaming its hash first, so uses that
+//! arrive late land in a fresh hash and are picked up by the next run.
+
+use chrono::{Duration, NaiveDate, Utc};
+use erp_core::permission_usage::{
+    PermissionUsageRecorder, PermissionUsageReport, PermissionUse, RoleGrants, UserPermissionUsage,
+};
+use erp_core::{DatabasePool, Error, ErrorCode, PermissionUsageConfig, Result, TenantContext, TenantId};
+use prometheus::IntCounter;
+use redis::aio::ConnectionManager;
+use redis::AsyncCommands;
+use sqlx::Row;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::retention::next_run;
+
+const OVERRIDE_SUFFIX: &str = "|override";
+
+fn day_key(date: NaiveDate) -> String {
+    format!("permission_usage:{}", date)
+}
+
+fn flushing_key(date: NaiveDate) -> String {
+    format!("permission_usage_flushing:{}", date)
+}
+
+fn counter_field(usage: &PermissionUse) -> String {
+    let suffix = if usage.via_override { OVERRIDE_SUFFIX } else { "" };
+    format!("{}|{}|{}{}", usage.tenant_id, usage.user_id, usage.permission, suffix)
+}
+
+/// Tenant, user, permission and whether the count is of override uses
+fn parse_counter_field(field: &str) -> Option<(Uuid, Uuid, String, bool)> {
+    let (field, via_override) = match field.strip_suffix(OVERRIDE_SUFFIX) {
+        Some(field) => (field, true),
+        None => (field, false),
+    };
+    let mut parts = field.splitn(3, '|');
+    let tenant_id = parts.next()?.parse().ok()?;
+    let user_id = parts.next()?.parse().ok()?;
+    let permission = parts.next().filter(|p| !p.is_empty())?.to_string();
+    Some((tenant_id, user_id, permission, via_override))
+}
+
+pub struct PermissionUsageService {
+    db: DatabasePool,
+    redis: ConnectionManager,
+    config: PermissionUsageConfig,
+    recorder: PermissionUsageRecorder,
+}
+
+impl PermissionUsageService {
+    /// The service and the channel `spawn` drains
+    pub fn new(
+        db: DatabasePool,
+        redis: ConnectionManager,
+        config: PermissionUsageConfig,
+        namespace: &str,
+    ) -> std::result::Result<(Self, mpsc::Receiver<PermissionUse>), String> {
+        let (recorder, uses) = PermissionUsageRecorder::new(config.channel_capacity, namespace)?;
+        Ok((Self { db, redis, config, recorder }, uses))
+    }
+
+    /// Recorder to put in the request extensions, if tracking is enabled
+    pub fn recorder(&self) -> Option<PermissionUsageRecorder> {
+        self.config.enabled.then(|| self.recorder.clone())
+    }
+
+    /// Counter of uses dropped because the channel was full
+    pub fn dropped_counter(&self) -> IntCounter {
+        self.recorder.dropped_counter()
+    }
+
+    pub fn config(&self) -> &PermissionUsageConfig {
+        &self.config
+    }
+
+    /// Start the Redis writer and the nightly flush
+    pub fn spawn(self: &Arc<Self>, uses: mpsc::Receiver<PermissionUse>) {
+        if !self.config.enabled {
+            return;
+        }
+
+        tokio::spawn(Arc::clone(self).write_loop(uses));
+
+        let service = Arc::clone(self);
+        tokio::spawn(async move {
+            loop {
+                let now = Utc::now();
+                let wait = (next_run(now, service.config.flush_at_hour_utc) - now)
+                    .to_std()
+                    .unwrap_or_default();
+                tokio::time::sleep(wait).await;
+
+                match service.flush_finished_days(Utc::now().date_naive()).await {
+                    Ok(rows) => info!("Flushed {} permission usage counters", rows),
+                    Err(e) => warn!("Permission usage flush failed: {}", e),
+                }
+            }
+        });
+    }
+
+    async fn write_loop(self: Arc<Self>, mut uses: mpsc::Receiver<PermissionUse>) {
+        let mut ticker = tokio::time::interval(std::time::Duration::from_millis(self.config.write_interval_ms.max(10)));
+        let mut pending: HashMap<(NaiveDate, String), i64> = HashMap::new();
+
+        loop {
+            tokio::select! {
+                usage = uses.recv() => match usage {
+                    Some(usage) => *pending.entry((usage.date, counter_field(&usage))).or_default() += 1,
+                    None => {
+                        self.write_counts(&mut pending).await;
+                        return;
+                    }
+                },
+                _ = ticker.tick() => self.write_counts(&mut pending).await,
+            }
+        }
+    }
+
+    /// Add the pending counts to Redis in one round trip; kept for the next tick if Redis fails
+    async fn write_counts(&self, pending: &mut HashMap<(NaiveDate, String), i64>) {
+        if pending.is_empty() {
+            return;
+        }
+
+        let ttl = self.config.redis_retention_days.max(1) * 86_400;
+        let mut pipe = redis::pipe();
+        let mut days = HashSet::new();
+        for ((date, field), count) in pending.iter() {
+            pipe.hincr(day_key(*date), field, *count).ignore();
+            days.insert(*date);
+        }
+        for date in days {
+            pipe.expire(day_key(date), ttl).ignore();
+        }
+
+        let mut conn = self.redis.clone();
+        match pipe.query_async::<()>(&mut conn).await {
+            Ok(()) => pending.clear(),
+            Err(e) => {
+                warn!("Failed to write permission usage counters: {}", e);
+                // Bounded like the channel; losing counts beats growing without limit
+                if pending.len() > self.config.channel_capacity {
+                    pending.clear();
+                }
+            }
+        }
+    }
+
+    /// Move every finished day still in Redis into `permission_usage_daily`
+    pub async fn flush_finished_days(&self, today: NaiveDate) -> Result<u64> {
+        let mut rows = 0;
+        for age in 1..=self.config.redis_retention_days.max(1) {
+            rows += self.flush_day(today - Duration::days(age)).await?;
+        }
+        Ok(rows)
+    }
+
+    async fn flush_day(&self, date: NaiveDate) -> Result<u64> {
+        let mut conn = self.redis.clone();
+        let flushing = flushing_key(date);
+        let mut rows = 0;
+
+        // A hash left over from a failed run goes first, then the day's own
+        for _ in 0..2 {
+            if !conn.exists::<_, bool>(&flushing).await? {
+                let renamed: redis::RedisResult<()> =
+                    redis::cmd("RENAME").arg(day_key(date)).arg(&flushing).query_async(&mut conn).await;
+                if renamed.is_err() {
+                    // No usage that day
+                    break;
+                }
+            }
+
+            let counts: HashMap<String, i64> = conn.hgetall(&flushing).await?;
+            rows += self.store_counts(date, &counts).await?;
+            conn.del::<_, ()>(&flushing).await?;
+        }
+        Ok(rows)
+    }
+
+    async fn store_counts(&self, date: NaiveDate, counts: &HashMap<String, i64>) -> Result<u64> {
+        let mut totals: BTreeMap<(Uuid, Uuid, String), (i64, i64)> = BTreeMap::new();
+        for (field, count) in counts {
+            match parse_counter_field(field) {
+                Some((tenant_id, user_id, permission, via_override)) => {
+                    let entry = totals.entry((tenant_id, user_id, permission)).or_default();
+                    if via_override {
+                        entry.1 += count;
+                    } else {
+                        entry.0 += count;
+                    }
+                }
+                None => warn!("Skipping malformed permission usage counter '{}'", field),
+            }
+        }
+        if totals.is_empty() {
+            return Ok(0);
+        }
+
+        let mut tenant_ids = Vec::with_capacity(totals.len());
+        let mut user_ids = Vec::with_capacity(totals.len());
+        let mut permissions = Vec::with_capacity(totals.len());
+        let mut uses = Vec::with_capacity(totals.len());
+        let mut override_uses = Vec::with_capacity(totals.len());
+        for ((tenant_id, user_id, permission), (direct, overridden)) in totals {
+            tenant_ids.push(tenant_id);
+            user_ids.push(user_id);
+            permissions.push(permission);
+            uses.push(direct);
+            override_uses.push(overridden);
+        }
+
+        let result = sqlx::query(
+            "INSERT INTO public.permission_usage_daily \
+             (tenant_id, user_id, permission, usage_date, uses, override_uses) \
+             SELECT t.tenant_id, t.user_id, t.permission, $4, t.uses, t.override_uses \
+             FROM UNNEST($1::UUID[], $2::UUID[], $3::TEXT[], $5::BIGINT[], $6::BIGINT[]) \
+                 AS t(tenant_id, user_id, permission, uses, override_uses) \
+             ON CONFLICT (tenant_id, usage_date, user_id, permission) DO UPDATE SET \
+                 uses = permission_usage_daily.uses + EXCLUDED.uses, \
+                 override_uses = permission_usage_daily.override_uses + EXCLUDED.override_uses",
+        )
+        .bind(&tenant_ids)
+        .bind(&user_ids)
+        .bind(&permissions)
+        .bind(date)
+        .bind(&uses)
+        .bind(&override_uses)
+        .execute(&self.db.main_pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Usage per role of `tenant_id` since `since`, optionally for one role only
+    pub async fn report(&self, tenant_id: Uuid, role: Option<&str>, since: NaiveDate) -> Result<PermissionUsageReport> {
+        let schema_name: String =
+            sqlx::query_scalar("SELECT schema_name FROM public.tenants WHERE id = $1 AND status <> 'deleted'")
+                .bind(tenant_id)
+                .fetch_optional(&self.db.main_pool)
+                .await?
+                .ok_or_else(|| Error::new(ErrorCode::NotFound, format!("Tenant {} not found", tenant_id)))?;
+
+        let tenant = TenantContext {
+            tenant_id: TenantId(tenant_id),
+            schema_name,
+        };
+        let pool = self.db.get_tenant_pool(&tenant).await?;
+        let role_rows = sqlx::query(
+            "SELECT r.id, r.name, \
+                 COALESCE(array_agg(DISTINCT p.resource || ':' || p.action) FILTER (WHERE p.id IS NOT NULL), '{}') AS permissions, \
+                 COALESCE(array_agg(DISTINCT ur.user_id) FILTER (WHERE ur.user_id IS NOT NULL), '{}') AS members \
+             FROM roles r \
+             LEFT JOIN role_permissions rp ON rp.role_id = r.id \
+             LEFT JOIN permissions p ON p.id = rp.permission_id \
+             LEFT JOIN user_roles ur ON ur.role_id = r.id \
+             WHERE $1::TEXT IS NULL OR lower(r.name) = lower($1) \
+             GROUP BY r.id, r.name ORDER BY r.name",
+        )
+        .bind(role)
+        .fetch_all(pool.get())
+        .await?;
+        let roles: Vec<RoleGrants> = role_rows
+            .iter()
+            .map(|row| RoleGrants {
+                role_id: row.get("id"),
+                role: row.get("name"),
+                permissions: row.get("permissions"),
+                members: row.get("members"),
+            })
+            .collect();
+        if let (Some(role), true) = (role, roles.is_empty()) {
+            return Err(Error::new(ErrorCode::NotFound, format!("Role '{}' not found", role)));
+        }
+
+        let usage_rows = sqlx::query(
+            "SELECT user_id, permission, SUM(uses)::BIGINT AS uses, \
+                 SUM(override_uses)::BIGINT AS override_uses, MAX(usage_date) AS last_used \
+             FROM public.permission_usage_daily \
+             WHERE tenant_id = $1 AND usage_date >= $2 \
+             GROUP BY user_id, permission",
+        )
+        .bind(tenant_id)
+        .bind(since)
+        .fetch_all(&self.db.main_pool)
+        .await?;
+        let mut usage: Vec<UserPermissionUsage> = usage_rows
+            .iter()
+            .map(|row| UserPermissionUsage {
+                user_id: row.get("user_id"),
+                permission: row.get("permission"),
+                uses: row.get("uses"),
+                override_uses: row.get("override_uses"),
+                last_used: row.get("last_used"),
+            })
+            .collect();
+        usage.extend(self.unflushed_usage(tenant_id, since).await?);
+
+        Ok(PermissionUsageReport::build(tenant_id, since, roles, &usage))
+    }
+
+    /// Last day each permission was used by anyone in `tenant_id`
+    pub async fn last_used(&self, tenant_id: Uuid) -> Result<HashMap<String, NaiveDate>> {
+        let rows = sqlx::query(
+            "SELECT permission, MAX(usage_date) AS last_used FROM public.permission_usage_daily \
+             WHERE tenant_id = $1 GROUP BY permission",
+        )
+        .bind(tenant_id)
+        .fetch_all(&self.db.main_pool)
+        .await?;
+        let mut last_used: HashMap<String, NaiveDate> = rows
+            .iter()
+            .map(|row| (row.get("permission"), row.get("last_used")))
+            .collect();
+
+        let today = Utc::now().date_naive();
+        let since = today - Duration::days(self.config.redis_retention_days.max(1));
+        for usage in self.unflushed_usage(tenant_id, since).await? {
+            let day = last_used.entry(usage.permission).or_insert(usage.last_used);
+            *day = (*day).max(usage.last_used);
+        }
+        Ok(last_used)
+    }
+
+    /// Today's counts, and those of any day the nightly flush has not reached yet
+    async fn unflushed_usage(&self, tenant_id: Uuid, since: NaiveDate) -> Result<Vec<UserPermissionUsage>> {
+        let today = Utc::now().date_naive();
+        let oldest = since.max(today - Duration::days(self.config.redis_retention_days.max(1)));
+        let mut conn = self.redis.clone();
+        let mut usage = Vec::new();
+
+        let mut date = oldest;
+        while date <= today {
+            for key in [day_key(date), flushing_key(date)] {
+                let mut fields = conn.hscan_match::<_, _, (String, i64)>(&key, format!("{}|*", tenant_id)).await?;
+                let mut counts = Vec::new();
+                while let Some(entry) = fields.next_item().await {
+                    counts.push(entry);
+                }
+                drop(fields);
+
+                for (field, count) in counts {
+                    if let Some((_, user_id, permission, via_override)) = parse_counter_field(&field) {
+                        usage.push(UserPermissionUsage {
+                            user_id,
+                            permission,
+                            uses: if via_override { 0 } else { count },
+                            override_uses: if via_override { count } else { 0 },
+                            last_used: date,
+                        });
+                    }
+                }
+            }
+            date += Duration::days(1);
+        }
+        Ok(usage)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counter_fields_round_trip() {
+        let usage = PermissionUse {
+            tenant_id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            permission: "products:write".to_string(),
+            via_override: true,
+            date: NaiveDate::from_ymd_opt(2026, 10, 16).unwrap(),
+        };
+        let field = counter_field(&usage);
+        assert!(field.ends_with("|products:write|override"));
+        assert_eq!(
+            parse_counter_field(&field),
+            Some((usage.tenant_id, usage.user_id, "products:write".to_string(), true))
+        );
+
+        let direct = PermissionUse { via_override: false, ..usage.clone() };
+        assert_eq!(parse_counter_field(&counter_field(&direct)).map(|f| f.3), Some(false));
+        assert_eq!(parse_counter_field("not|a|counter"), None);
+    }
+}
@@ -0,0 +1,388 @@
+//! # Plan Limit Monitoring
+//!
+//! Nightly job measuring every active tenant against the limits of its plan
+//! (see [`erp_core::plan_limits`]) at `plan_limits.run_at_hour_utc`. The
+//! measurements are stored per day; a limit whose usage crosses one of
+//! `thresholds` raises a `plan_limit` notification to the tenant's holders of
+//! `alert_permission` and an email to the account managers. Each threshold is
+//! notified once per crossing: only after usage fell below it is it notified
+//! again.
+//!
+//! [`PlanLimitService::report`] gives the usage endpoint the current values,
+//! their share of the limits and how they moved over the last 7 and 30 days.
+
+use chrono::{Duration, NaiveDate, Utc};
+use erp_auth::{email::EmailReference, EmailService};
+use erp_core::plan_limits::{limit_usage, threshold_crossing, usage_percent, LimitUsage, PlanResource, PlanUsageStore, UsageMeasurement, TREND_DAYS};
+use erp_core::{PlanLimitsConfig, Result};
+use serde::Serialize;
+use std::sync::Arc;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::notification_digest::{Notification, NotificationService, NotificationSeverity};
+use crate::retention::next_run;
+use crate::transfer_approvals::PermissionHolders;
+
+/// Notification type stored in `notification_preferences`
+pub const PLAN_LIMIT: &str = "plan_limit";
+
+/// A threshold of a limit crossed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LimitAlert {
+    pub tenant_id: Uuid,
+    pub resource: PlanResource,
+    /// Percent of the limit
+    pub threshold: u32,
+    pub value: i64,
+    pub limit: i64,
+}
+
+/// What one nightly run measured
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PlanLimitRun {
+    pub tenants: u64,
+    pub measured: u64,
+    pub alerts: Vec<LimitAlert>,
+}
+
+/// Holders of the alert permission, the notification pipeline and the account managers' email
+type AlertSinks = (Arc<dyn PermissionHolders>, Arc<NotificationService>, Arc<EmailService>);
+
+/// Measures tenants against their plan limits and alerts on thresholds
+pub struct PlanLimitService {
+    store: Arc<dyn PlanUsageStore>,
+    config: PlanLimitsConfig,
+    /// Without them alerts are only logged
+    alerts: Option<AlertSinks>,
+}
+
+impl PlanLimitService {
+    pub fn new(store: Arc<dyn PlanUsageStore>, config: PlanLimitsConfig) -> Self {
+        Self {
+            store,
+            config,
+            alerts: None,
+        }
+    }
+
+    /// Notify the holders of `alert_permission` and email the account managers
+    pub fn with_alerts(
+        mut self,
+        holders: Arc<dyn PermissionHolders>,
+        notifications: Arc<NotificationService>,
+        email: Arc<EmailService>,
+    ) -> Self {
+        self.alerts = Some((holders, notifications, email));
+        self
+    }
+
+    /// Measure every active tenant on `today`
+    pub async fn run(&self, today: NaiveDate) -> Result<PlanLimitRun> {
+        let mut run = PlanLimitRun::default();
+        for tenant_id in self.store.tenants().await? {
+            run.tenants += 1;
+            match self.run_tenant(tenant_id, today).await {
+                Ok((measured, alerts)) => {
+                    run.measured += measured;
+                    run.alerts.extend(alerts);
+                }
+                Err(e) => warn!("Plan limit measurement of tenant {} failed: {}", tenant_id, e),
+            }
+        }
+        Ok(run)
+    }
+
+    async fn run_tenant(&self, tenant_id: Uuid, today: NaiveDate) -> Result<(u64, Vec<LimitAlert>)> {
+        let limits = self.store.limits(tenant_id).await?;
+        let mut measurements = Vec::with_capacity(PlanResource::ALL.len());
+        for resource in PlanResource::ALL {
+            let value = self.store.measure(tenant_id, resource, false).await?;
+            measurements.push(UsageMeasurement {
+                usage_date: today,
+                resource,
+                value,
+            });
+        }
+        self.store.record(tenant_id, &measurements).await?;
+
+        let mut alerts = Vec::new();
+        for measurement in &measurements {
+            let notified = self.store.notified_threshold(tenant_id, measurement.resource).await?;
+            // A limit removed forgets its notifications
+            let Some(limit) = limits.limit(measurement.resource) else {
+                if notified.is_some() {
+                    self.store.set_notified_threshold(tenant_id, measurement.resource, None).await?;
+                }
+                continue;
+            };
+            let crossing = threshold_crossing(&self.config.thresholds, notified, usage_percent(measurement.value, limit));
+            if crossing.level != notified {
+                self.store
+                    .set_notified_threshold(tenant_id, measurement.resource, crossing.level)
+                    .await?;
+            }
+            if let (true, Some(threshold)) = (crossing.notify, crossing.level) {
+                let alert = LimitAlert {
+                    tenant_id,
+                    resource: measurement.resource,
+                    threshold,
+                    value: measurement.value,
+                    limit,
+                };
+                self.alert(&alert).await;
+                alerts.push(alert);
+            }
+        }
+        Ok((measurements.len() as u64, alerts))
+    }
+
+    async fn alert(&self, alert: &LimitAlert) {
+        info!(
+            "Tenant {} reached {}% of its {} limit ({} of {})",
+            alert.tenant_id, alert.threshold, alert.resource.as_str(), alert.value, alert.limit
+        );
+        let Some((holders, notifications, email)) = &self.alerts else {
+            return;
+        };
+
+        let title = format!("{}% of the plan's {} limit reached", alert.threshold, alert.resource.label());
+        let body = format!(
+            "{} of {} {} are in use. At the limit creating more may be refused; an upgrade of the plan raises it.",
+            alert.value,
+            alert.limit,
+            alert.resource.label()
+        );
+        match holders.users_with_permission(alert.tenant_id, &self.config.alert_permission).await {
+            Ok(user_ids) => {
+                let now = Utc::now();
+                for user_id in user_ids {
+                    let notification = Notification {
+                        tenant_id: alert.tenant_id,
+                        user_id,
+                        category: PLAN_LIMIT.to_string(),
+                        severity: if alert.threshold >= 100 {
+                            NotificationSeverity::Critical
+                        } else {
+                            NotificationSeverity::Warning
+                        },
+                        title: title.clone(),
+                        body: Some(body.clone()),
+                        location_id: None,
+                        location_name: None,
+                        customer_id: None,
+                        customer_name: None,
+                        link: Some("/settings/usage".to_string()),
+                        created_at: now,
+                    };
+                    if let Err(e) = notifications.notify(&notification).await {
+                        warn!("Failed to notify user {} about a plan limit: {}", user_id, e);
+                    }
+                }
+            }
+            Err(e) => warn!("Failed to look up recipients of plan limit alert: {}", e),
+        }
+
+        let subject = format!("Tenant {}: {}", alert.tenant_id, title);
+        let html = format!("<p>{}</p>", body);
+        let reference = EmailReference {
+            tenant_id: alert.tenant_id,
+            customer_id: None,
+            sent_by: None,
+        };
+        for address in &self.config.account_manager_emails {
+            if let Err(e) = email
+                .send_email_with_reference(address, &subject, &html, Some(&body), Some(&reference))
+                .await
+            {
+                warn!("Failed to email account manager {} about a plan limit: {}", address, e);
+            }
+        }
+    }
+
+    /// Current usage of the tenant against each limit, with its trend
+    pub async fn report(&self, tenant_id: Uuid, today: NaiveDate) -> Result<Vec<LimitUsage>> {
+        let limits = self.store.limits(tenant_id).await?;
+        // A week more than the trend, so a missed night still leaves a baseline
+        let history = self.store.history(tenant_id, today - Duration::days(TREND_DAYS + 7)).await?;
+        let mut usage = Vec::with_capacity(PlanResource::ALL.len());
+        for resource in PlanResource::ALL {
+            let current = self.store.measure(tenant_id, resource, false).await?;
+            usage.push(limit_usage(resource, &limits, current, &history, today));
+        }
+        Ok(usage)
+    }
+
+    /// Run daily at `run_at_hour_utc` in the background
+    pub fn spawn(self: &Arc<Self>) -> Option<tokio::task::JoinHandle<()>> {
+        if !self.config.enabled {
+            return None;
+        }
+
+        let service = Arc::clone(self);
+        Some(tokio::spawn(async move {
+            loop {
+                let now = Utc::now();
+                let wait = (next_run(now, service.config.run_at_hour_utc) - now).to_std().unwrap_or_default();
+                tokio::time::sleep(wait).await;
+
+                match service.run(Utc::now().date_naive()).await {
+                    Ok(run) if run.alerts.is_empty() => {}
+                    Ok(run) => info!(
+                        "Plan limits: {} tenants measured, {} thresholds crossed",
+                        run.tenants,
+                        run.alerts.len()
+                    ),
+                    Err(e) => warn!("Plan limit run failed: {}", e),
+                }
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use erp_core::plan_limits::PlanLimits;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    /// One tenant's limits, current values, measurements and notified thresholds
+    #[derive(Default)]
+    struct MemoryStore {
+        limits: PlanLimits,
+        values: Mutex<HashMap<PlanResource, i64>>,
+        recorded: Mutex<Vec<UsageMeasurement>>,
+        notified: Mutex<HashMap<PlanResource, u32>>,
+    }
+
+    impl MemoryStore {
+        fn set(&self, resource: PlanResource, value: i64) {
+            self.values.lock().unwrap().insert(resource, value);
+        }
+    }
+
+    #[async_trait]
+    impl PlanUsageStore for MemoryStore {
+        async fn tenants(&self) -> Result<Vec<Uuid>> {
+            Ok(vec![Uuid::from_u128(1)])
+        }
+
+        async fn limits(&self, _tenant_id: Uuid) -> Result<PlanLimits> {
+            Ok(self.limits.clone())
+        }
+
+        async fn measure(&self, _tenant_id: Uuid, resource: PlanResource, _exact: bool) -> Result<i64> {
+            Ok(self.values.lock().unwrap().get(&resource).copied().unwrap_or(0))
+        }
+
+        async fn record(&self, _tenant_id: Uuid, measurements: &[UsageMeasurement]) -> Result<()> {
+            self.recorded.lock().unwrap().extend_from_slice(measurements);
+            Ok(())
+        }
+
+        async fn history(&self, _tenant_id: Uuid, since: NaiveDate) -> Result<Vec<UsageMeasurement>> {
+            Ok(self.recorded.lock().unwrap().iter().filter(|m| m.usage_date >= since).copied().collect())
+        }
+
+        async fn notified_threshold(&self, _tenant_id: Uuid, resource: PlanResource) -> Result<Option<u32>> {
+            Ok(self.notified.lock().unwrap().get(&resource).copied())
+        }
+
+        async fn set_notified_threshold(&self, _tenant_id: Uuid, resource: PlanResource, threshold: Option<u32>) -> Result<()> {
+            let mut notified = self.notified.lock().unwrap();
+            match threshold {
+                Some(threshold) => notified.insert(resource, threshold),
+                None => notified.remove(&resource),
+            };
+            Ok(())
+        }
+    }
+
+    fn day(n: i64) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 1, 1).unwrap() + Duration::days(n)
+    }
+
+    fn service(store: Arc<MemoryStore>) -> PlanLimitService {
+        PlanLimitService::new(store, PlanLimitsConfig::default())
+    }
+
+    #[tokio::test]
+    async fn test_threshold_alerted_once_per_crossing() {
+        let store = Arc::new(MemoryStore {
+            limits: PlanLimits {
+                customers: Some(1000),
+                storage_bytes: Some(10_000),
+                ..PlanLimits::default()
+            },
+            ..MemoryStore::default()
+        });
+        let service = service(store.clone());
+        let thresholds = |run: PlanLimitRun| run.alerts.iter().map(|a| (a.resource, a.threshold)).collect::<Vec<_>>();
+
+        store.set(PlanResource::Customers, 850);
+        // Products and users have no limit and are only measured
+        store.set(PlanResource::Products, 5_000);
+        let run = service.run(day(0)).await.unwrap();
+        assert_eq!(run.tenants, 1);
+        assert_eq!(run.measured, 4);
+        assert_eq!(thresholds(run), vec![(PlanResource::Customers, 80)]);
+
+        // Still above 80% the next nights: nothing new
+        store.set(PlanResource::Customers, 900);
+        assert!(service.run(day(1)).await.unwrap().alerts.is_empty());
+
+        // 95% and storage at its limit on the same night
+        store.set(PlanResource::Customers, 960);
+        store.set(PlanResource::StorageBytes, 10_000);
+        assert_eq!(
+            thresholds(service.run(day(2)).await.unwrap()),
+            vec![(PlanResource::Customers, 95), (PlanResource::StorageBytes, 100)]
+        );
+        assert!(service.run(day(3)).await.unwrap().alerts.is_empty());
+
+        // Customers deleted below 80%, then growing back over it
+        store.set(PlanResource::Customers, 700);
+        assert!(service.run(day(4)).await.unwrap().alerts.is_empty());
+        assert_eq!(store.notified.lock().unwrap().get(&PlanResource::Customers), None);
+        store.set(PlanResource::Customers, 810);
+        assert_eq!(thresholds(service.run(day(5)).await.unwrap()), vec![(PlanResource::Customers, 80)]);
+
+        assert_eq!(store.recorded.lock().unwrap().len(), 6 * 4);
+    }
+
+    #[tokio::test]
+    async fn test_report_from_recorded_history() {
+        let store = Arc::new(MemoryStore {
+            limits: PlanLimits {
+                products: Some(2000),
+                enforced: true,
+                ..PlanLimits::default()
+            },
+            ..MemoryStore::default()
+        });
+        let service = service(store.clone());
+        for (n, products) in [(0, 1000), (23, 1300), (30, 1400)] {
+            store.set(PlanResource::Products, products);
+            service.run(day(n)).await.unwrap();
+        }
+        store.set(PlanResource::Products, 1500);
+
+        let report = service.report(Uuid::from_u128(1), day(30)).await.unwrap();
+        assert_eq!(report.len(), PlanResource::ALL.len());
+        let products = report.iter().find(|u| u.resource == PlanResource::Products).unwrap();
+        assert_eq!(products.limit, Some(2000));
+        assert_eq!(products.current, 1500);
+        assert_eq!(products.percent, Some(75.0));
+        assert!(products.enforced);
+        assert_eq!(products.change_7d, Some(200));
+        assert_eq!(products.change_30d, Some(500));
+        // 500 in 30 days, 500 to go
+        assert_eq!(products.days_to_limit, Some(30));
+
+        let customers = report.iter().find(|u| u.resource == PlanResource::Customers).unwrap();
+        assert_eq!(customers.limit, None);
+        assert_eq!(customers.percent, None);
+    }
+}
@@ -0,0 +1,89 @@
+//! # Product Lifecycle Automation
+//!
+//! Daily job running the lifecycle automation
+//! (see [`erp_master_data::product::lifecycle`]) for every active tenant at
+//! `product_lifecycle.run_at_hour_utc`. Depending on the tenant's mode the
+//! transitions the rules call for are proposed in the product suggestion
+//! review or applied, with their evidence recorded on the lifecycle.
+
+use chrono::{DateTime, Utc};
+use erp_core::{ProductLifecycleConfig, SYSTEM_ACTOR_ID};
+use erp_master_data::product::{
+    DefaultLifecycleAutomationService, LifecycleAutomationService, LifecycleRun, ProductLifecycleRepository,
+};
+use erp_master_data::TenantContext;
+use sqlx::{PgPool, Row};
+use std::sync::Arc;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::retention::next_run;
+use crate::state::lifecycle_rules;
+
+/// Lifecycle automation of every active tenant
+pub struct ProductLifecycleJob {
+    pool: PgPool,
+    repository: Arc<dyn ProductLifecycleRepository>,
+    config: ProductLifecycleConfig,
+}
+
+impl ProductLifecycleJob {
+    pub fn new(pool: PgPool, repository: Arc<dyn ProductLifecycleRepository>, config: ProductLifecycleConfig) -> Self {
+        Self {
+            pool,
+            repository,
+            config,
+        }
+    }
+
+    /// Run the automation for every active tenant; returns the totals
+    pub async fn run_all(&self, now: DateTime<Utc>) -> Result<LifecycleRun, String> {
+        let tenants = sqlx::query("SELECT id, schema_name FROM public.tenants WHERE status = 'active' ORDER BY id")
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let mut total = LifecycleRun::default();
+        for row in tenants {
+            let tenant_id: Uuid = row.get("id");
+            // Changes the job makes are recorded as made by the system actor
+            let context = TenantContext::new(tenant_id, row.get("schema_name"), SYSTEM_ACTOR_ID);
+            let service = DefaultLifecycleAutomationService::new(self.repository.clone(), context, lifecycle_rules(&self.config));
+            match service.run(now).await {
+                Ok(run) => {
+                    total.evaluated += run.evaluated;
+                    total.suggested += run.suggested;
+                    total.applied += run.applied;
+                    total.frozen += run.frozen;
+                }
+                Err(e) => warn!("Lifecycle automation of tenant {} failed: {}", tenant_id, e),
+            }
+        }
+        Ok(total)
+    }
+
+    /// Run daily at `run_at_hour_utc` in the background
+    pub fn spawn(self: &Arc<Self>) -> Option<tokio::task::JoinHandle<()>> {
+        if !self.config.enabled {
+            return None;
+        }
+
+        let job = Arc::clone(self);
+        Some(tokio::spawn(async move {
+            loop {
+                let now = Utc::now();
+                let wait = (next_run(now, job.config.run_at_hour_utc) - now).to_std().unwrap_or_default();
+                tokio::time::sleep(wait).await;
+
+                match job.run_all(Utc::now()).await {
+                    Ok(run) if run.suggested == 0 && run.applied == 0 => {}
+                    Ok(run) => info!(
+                        "Lifecycle automation: {} products evaluated, {} transitions suggested, {} applied",
+                        run.evaluated, run.suggested, run.applied
+                    ),
+                    Err(e) => warn!("Lifecycle automation run failed: {}", e),
+                }
+            }
+        }))
+    }
+}
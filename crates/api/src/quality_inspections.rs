@@ -0,0 +1,171 @@
+//! # Quality Inspection Scheduling
+//!
+//! Daily job for the inspection calendar
+//! (see [`erp_master_data::product::quality`]) at
+//! `quality_inspections.run_at_hour_utc`:
+//!
+//! - every active tenant's inspection rules are run, scheduling incoming-goods
+//!   inspections of received batches the receipt could not schedule and
+//!   re-inspections ahead of certification expiries
+//! - inspections still open after their scheduled date are reported once to
+//!   the inspector and the holders of `products:quality_inspect`
+//!
+//! Notifications use the `quality_inspection` category, so users choose
+//! between immediate emails and the daily digest like for any other category.
+
+use chrono::{DateTime, Utc};
+use erp_core::{QualityInspectionConfig, Result, SYSTEM_ACTOR_ID};
+use erp_master_data::product::{
+    DefaultQualityInspectionService, InspectionRun, QualityInspection, QualityInspectionRepository,
+    QualityInspectionService, QUALITY_INSPECTION_PERMISSION,
+};
+use erp_master_data::TenantContext;
+use sqlx::{PgPool, Row};
+use std::sync::Arc;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::notification_digest::{Notification, NotificationService, NotificationSeverity};
+use crate::retention::next_run;
+use crate::state::inspection_rules;
+use crate::transfer_approvals::{recipients, PermissionHolders};
+
+/// Notification type stored in `notification_preferences`
+pub const QUALITY_INSPECTION: &str = "quality_inspection";
+
+fn link(inspection_id: Uuid) -> String {
+    format!("/products/inspections/{}", inspection_id)
+}
+
+/// Inspection rules of every active tenant and overdue alerts
+pub struct QualityInspectionJob {
+    pool: PgPool,
+    repository: Arc<dyn QualityInspectionRepository>,
+    holders: Arc<dyn PermissionHolders>,
+    notifications: Arc<NotificationService>,
+    config: QualityInspectionConfig,
+}
+
+impl QualityInspectionJob {
+    pub fn new(
+        pool: PgPool,
+        repository: Arc<dyn QualityInspectionRepository>,
+        holders: Arc<dyn PermissionHolders>,
+        notifications: Arc<NotificationService>,
+        config: QualityInspectionConfig,
+    ) -> Self {
+        Self {
+            pool,
+            repository,
+            holders,
+            notifications,
+            config,
+        }
+    }
+
+    /// Run the inspection rules for every active tenant; returns the totals
+    pub async fn run_all(&self, now: DateTime<Utc>) -> Result<InspectionRun> {
+        let tenants = sqlx::query("SELECT id, schema_name FROM public.tenants WHERE status = 'active' ORDER BY id")
+            .fetch_all(&self.pool)
+            .await?;
+
+        let mut total = InspectionRun::default();
+        for row in tenants {
+            let tenant_id: Uuid = row.get("id");
+            // Inspections the job schedules are recorded as created by the system actor
+            let context = TenantContext::new(tenant_id, row.get("schema_name"), SYSTEM_ACTOR_ID);
+            let service = DefaultQualityInspectionService::new(self.repository.clone(), context, inspection_rules(&self.config));
+            match service.run_rules(now).await {
+                Ok(run) => {
+                    total.incoming += run.incoming;
+                    total.reinspections += run.reinspections;
+                }
+                Err(e) => warn!("Inspection rules of tenant {} failed: {}", tenant_id, e),
+            }
+        }
+        Ok(total)
+    }
+
+    async fn notify_overdue(&self, inspection: &QualityInspection, user_ids: &[Uuid], now: DateTime<Utc>) {
+        let days = (now - inspection.scheduled_date).num_days();
+        let body = format!(
+            "{} inspection of product {} was due {} ({} days ago)",
+            inspection.inspection_type,
+            inspection.product_id,
+            inspection.scheduled_date.date_naive(),
+            days
+        );
+        for user_id in user_ids {
+            let notification = Notification {
+                tenant_id: inspection.tenant_id,
+                user_id: *user_id,
+                category: QUALITY_INSPECTION.to_string(),
+                severity: NotificationSeverity::Warning,
+                title: "Overdue quality inspection".to_string(),
+                body: Some(body.clone()),
+                location_id: None,
+                location_name: None,
+                customer_id: None,
+                customer_name: None,
+                link: Some(link(inspection.id)),
+                created_at: now,
+            };
+            if let Err(e) = self.notifications.notify(&notification).await {
+                warn!("Failed to notify user {} about inspection {}: {}", user_id, inspection.id, e);
+            }
+        }
+    }
+
+    /// Report every inspection open past its scheduled date once; returns the number reported
+    pub async fn alert_overdue(&self, now: DateTime<Utc>) -> Result<usize> {
+        let overdue = self.repository.unalerted_overdue_inspections(now).await?;
+
+        for inspection in &overdue {
+            let inspectors = self
+                .holders
+                .users_with_permission(inspection.tenant_id, QUALITY_INSPECTION_PERMISSION)
+                .await?;
+            let assigned: Vec<Uuid> = inspection.inspector_id.into_iter().collect();
+            // Nobody is left out of the recipients: the job acts as the system actor, not a user
+            let user_ids = recipients(&[assigned, inspectors], SYSTEM_ACTOR_ID);
+            self.notify_overdue(inspection, &user_ids, now).await;
+
+            self.repository
+                .mark_overdue_alerted(inspection.tenant_id, inspection.id, now)
+                .await?;
+        }
+        Ok(overdue.len())
+    }
+
+    /// Run daily at `run_at_hour_utc` in the background
+    pub fn spawn(self: &Arc<Self>) -> Option<tokio::task::JoinHandle<()>> {
+        if !self.config.enabled {
+            return None;
+        }
+
+        let job = Arc::clone(self);
+        Some(tokio::spawn(async move {
+            loop {
+                let now = Utc::now();
+                let wait = (next_run(now, job.config.run_at_hour_utc) - now).to_std().unwrap_or_default();
+                tokio::time::sleep(wait).await;
+
+                let now = Utc::now();
+                match job.run_all(now).await {
+                    Ok(run) if run.incoming == 0 && run.reinspections == 0 => {}
+                    Ok(run) => info!(
+                        "Quality inspections: {} incoming and {} re-inspections scheduled",
+                        run.incoming, run.reinspections
+                    ),
+                    Err(e) => warn!("Quality inspection run failed: {}", e),
+                }
+                match job.alert_overdue(now).await {
+                    Ok(0) => {}
+                    Ok(alerted) => info!("Reported {} overdue quality inspections", alerted),
+                    Err(e) => warn!("Overdue inspection alerts failed: {}", e),
+                }
+            }
+        }))
+    }
+}
+
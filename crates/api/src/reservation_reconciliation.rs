@@ -0,0 +1,566 @@
+//! # Reserved Quantity Reconciliation
+//!
+//! Nightly job keeping `location_items.quantity_reserved` equal to the stock
+//! held for each item (see [`erp_master_data::inventory::reservation_drift`]).
+//! It starts at `reservation_reconciliation.run_at_hour_utc` and checks the
+//! night's [`ReconciliationScope`]: one sixth of the items, or all of them on
+//! `full_sweep_weekday`. Items are read in batches with a pause between them
+//! so the run stays in the background of regular traffic.
+//!
+//! Drifted items are logged and, with `repair` on, corrected one at a time;
+//! each correction is stored as an adjustment. An item whose drift comes back
+//! (see `alert_threshold` and `alert_repeat_count`) or whose holds exceed its
+//! stock on hand raises a critical `reservation_drift` notification to the
+//! tenant's holders of `alert_permission`.
+
+use chrono::{DateTime, Duration, Utc, Weekday};
+use erp_core::calendar::weekday_from_iso;
+use erp_core::{Error, ErrorCode, ReservationReconciliationConfig, Result};
+use erp_master_data::inventory::reservation_drift::is_repeated_drift;
+use erp_master_data::inventory::{
+    ReconciliationScope, RepairOutcome, ReservationAdjustment, ReservationDrift, ReservationReconciliationRepository,
+};
+use serde::Serialize;
+use std::sync::Arc;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::notification_digest::{Notification, NotificationService, NotificationSeverity};
+use crate::retention::next_run;
+use crate::transfer_approvals::PermissionHolders;
+
+/// Notification type stored in `notification_preferences`
+pub const RESERVATION_DRIFT: &str = "reservation_drift";
+
+/// Corrections listed by the drift report
+const REPORT_ADJUSTMENTS: i64 = 50;
+
+/// Why an item needs a person to look at it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DriftAlert {
+    pub tenant_id: Uuid,
+    pub location_item_id: Uuid,
+    pub product_id: Uuid,
+    pub location_id: Uuid,
+    pub reason: String,
+}
+
+/// What one reconciliation run found and did
+#[derive(Debug, Clone, Serialize)]
+pub struct ReconciliationRun {
+    pub run_id: Uuid,
+    pub scope: ReconciliationScope,
+    pub repair: bool,
+    pub items_checked: u64,
+    pub drifted: u64,
+    pub corrected: u64,
+    /// Items whose holds exceed their stock on hand
+    pub refused: u64,
+    /// Items whose repair failed
+    pub failed: u64,
+    pub alerts: Vec<DriftAlert>,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+}
+
+/// Current drift of a tenant and its latest corrections
+#[derive(Debug, Clone, Serialize)]
+pub struct DriftReport {
+    pub items_checked: u64,
+    pub drifted: Vec<ReservationDrift>,
+    pub recent_adjustments: Vec<ReservationAdjustment>,
+    pub checked_at: DateTime<Utc>,
+}
+
+fn inventory_error(e: erp_master_data::MasterDataError) -> Error {
+    match e {
+        erp_master_data::MasterDataError::NotFoundError(message) => Error::new(ErrorCode::ResourceNotFound, message),
+        e => Error::new(ErrorCode::DatabaseError, e.to_string()),
+    }
+}
+
+/// Finds and corrects drift of reserved quantities
+pub struct ReservationReconciliationService {
+    repository: Arc<dyn ReservationReconciliationRepository>,
+    config: ReservationReconciliationConfig,
+    /// Without them alerts are only logged
+    alerts: Option<(Arc<dyn PermissionHolders>, Arc<NotificationService>)>,
+}
+
+impl ReservationReconciliationService {
+    pub fn new(repository: Arc<dyn ReservationReconciliationRepository>, config: ReservationReconciliationConfig) -> Self {
+        Self {
+            repository,
+            config,
+            alerts: None,
+        }
+    }
+
+    /// Send alerts as critical notifications to the holders of `alert_permission`
+    pub fn with_alerts(mut self, holders: Arc<dyn PermissionHolders>, notifications: Arc<NotificationService>) -> Self {
+        self.alerts = Some((holders, notifications));
+        self
+    }
+
+    /// Scope of the nightly run on the day of `now`
+    pub fn scope_for(&self, now: DateTime<Utc>) -> ReconciliationScope {
+        let full_sweep_day = weekday_from_iso(self.config.full_sweep_weekday).unwrap_or(Weekday::Sun);
+        ReconciliationScope::for_night(now.date_naive(), full_sweep_day)
+    }
+
+    /// Check every item of the scope across tenants, repairing drift when `repair` is on
+    pub async fn run(&self, scope: ReconciliationScope, now: DateTime<Utc>) -> Result<ReconciliationRun> {
+        let batch_size = self.config.batch_size.max(1);
+        let mut run = ReconciliationRun {
+            run_id: Uuid::new_v4(),
+            scope,
+            repair: self.config.repair,
+            items_checked: 0,
+            drifted: 0,
+            corrected: 0,
+            refused: 0,
+            failed: 0,
+            alerts: Vec::new(),
+            started_at: now,
+            finished_at: now,
+        };
+
+        let mut after = None;
+        loop {
+            let items = self
+                .repository
+                .measure_items(None, scope, after, batch_size)
+                .await
+                .map_err(inventory_error)?;
+            let Some(last) = items.last() else {
+                break;
+            };
+            after = Some(last.location_item_id);
+            run.items_checked += items.len() as u64;
+
+            for drift in items.iter().filter(|item| item.has_drift()) {
+                run.drifted += 1;
+                warn!(
+                    "Reserved quantity of item {} (product {}, location {}) is {} but {} are held",
+                    drift.location_item_id, drift.product_id, drift.location_id, drift.recorded_reserved, drift.held_reserved
+                );
+                if self.config.repair {
+                    self.repair_drift(drift, &mut run, now).await;
+                }
+            }
+
+            if (items.len() as i64) < batch_size {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(self.config.batch_pause_ms)).await;
+        }
+
+        run.finished_at = Utc::now();
+        Ok(run)
+    }
+
+    async fn repair_drift(&self, drift: &ReservationDrift, run: &mut ReconciliationRun, now: DateTime<Utc>) {
+        let outcome = match self
+            .repository
+            .repair_item(drift.tenant_id, drift.location_item_id, run.run_id, None)
+            .await
+        {
+            Ok(outcome) => outcome,
+            Err(e) => {
+                error!("Failed to repair reserved quantity of item {}: {}", drift.location_item_id, e);
+                run.failed += 1;
+                return;
+            }
+        };
+
+        match &outcome {
+            RepairOutcome::InSync => {}
+            RepairOutcome::Corrected { .. } => run.corrected += 1,
+            RepairOutcome::Refused { .. } => run.refused += 1,
+        }
+        if let Some(alert) = self.check_outcome(&outcome, now).await {
+            self.alert(&alert).await;
+            run.alerts.push(alert);
+        }
+    }
+
+    /// The alert an outcome calls for, if any
+    async fn check_outcome(&self, outcome: &RepairOutcome, now: DateTime<Utc>) -> Option<DriftAlert> {
+        match outcome {
+            RepairOutcome::InSync => None,
+            RepairOutcome::Refused { drift, reason } => Some(DriftAlert {
+                tenant_id: drift.tenant_id,
+                location_item_id: drift.location_item_id,
+                product_id: drift.product_id,
+                location_id: drift.location_id,
+                reason: format!("Reserved quantity could not be corrected: {}", reason),
+            }),
+            RepairOutcome::Corrected { adjustment } => {
+                let since = now - Duration::days(self.config.alert_window_days.max(1));
+                let earlier: Vec<i32> = match self.repository.item_adjustments(adjustment.location_item_id, since).await {
+                    Ok(adjustments) => adjustments
+                        .iter()
+                        .filter(|earlier| earlier.id != adjustment.id)
+                        .map(ReservationAdjustment::delta)
+                        .collect(),
+                    Err(e) => {
+                        warn!("Failed to load corrections of item {}: {}", adjustment.location_item_id, e);
+                        return None;
+                    }
+                };
+                is_repeated_drift(
+                    adjustment.delta(),
+                    &earlier,
+                    self.config.alert_threshold,
+                    self.config.alert_repeat_count,
+                )
+                .then(|| DriftAlert {
+                    tenant_id: adjustment.tenant_id,
+                    location_item_id: adjustment.location_item_id,
+                    product_id: adjustment.product_id,
+                    location_id: adjustment.location_id,
+                    reason: format!(
+                        "Reserved quantity drifted by {} again, {} corrections within {} days",
+                        adjustment.delta(),
+                        earlier.len() + 1,
+                        self.config.alert_window_days
+                    ),
+                })
+            }
+        }
+    }
+
+    async fn alert(&self, alert: &DriftAlert) {
+        error!("Reservation drift alert for item {}: {}", alert.location_item_id, alert.reason);
+        let Some((holders, notifications)) = &self.alerts else {
+            return;
+        };
+
+        let user_ids = match holders
+            .users_with_permission(alert.tenant_id, &self.config.alert_permission)
+            .await
+        {
+            Ok(user_ids) => user_ids,
+            Err(e) => {
+                warn!("Failed to look up recipients of reservation drift alert: {}", e);
+                return;
+            }
+        };
+        let now = Utc::now();
+        for user_id in user_ids {
+            let notification = Notification {
+                tenant_id: alert.tenant_id,
+                user_id,
+                category: RESERVATION_DRIFT.to_string(),
+                severity: NotificationSeverity::Critical,
+                title: "Reserved inventory keeps drifting".to_string(),
+                body: Some(format!(
+                    "Product {} at location {}: {}",
+                    alert.product_id, alert.location_id, alert.reason
+                )),
+                location_id: Some(alert.location_id),
+                location_name: None,
+                customer_id: None,
+                customer_name: None,
+                link: Some("/inventory/reservation-drift".to_string()),
+                created_at: now,
+            };
+            if let Err(e) = notifications.notify(&notification).await {
+                warn!("Failed to notify user {} about reservation drift: {}", user_id, e);
+            }
+        }
+    }
+
+    /// Items of the tenant drifting right now, and its latest corrections
+    pub async fn report(&self, tenant_id: Uuid) -> Result<DriftReport> {
+        let batch_size = self.config.batch_size.max(1);
+        let mut report = DriftReport {
+            items_checked: 0,
+            drifted: Vec::new(),
+            recent_adjustments: Vec::new(),
+            checked_at: Utc::now(),
+        };
+
+        let mut after = None;
+        loop {
+            let items = self
+                .repository
+                .measure_items(Some(tenant_id), ReconciliationScope::Full, after, batch_size)
+                .await
+                .map_err(inventory_error)?;
+            let Some(last) = items.last() else {
+                break;
+            };
+            after = Some(last.location_item_id);
+            report.items_checked += items.len() as u64;
+            let full_batch = items.len() as i64 == batch_size;
+            report.drifted.extend(items.into_iter().filter(ReservationDrift::has_drift));
+            if !full_batch {
+                break;
+            }
+        }
+
+        report.recent_adjustments = self
+            .repository
+            .list_adjustments(tenant_id, REPORT_ADJUSTMENTS)
+            .await
+            .map_err(inventory_error)?;
+        Ok(report)
+    }
+
+    /// Correct one item now, on behalf of `user_id`
+    pub async fn repair_item(&self, tenant_id: Uuid, location_item_id: Uuid, user_id: Uuid) -> Result<RepairOutcome> {
+        let outcome = self
+            .repository
+            .repair_item(tenant_id, location_item_id, Uuid::new_v4(), Some(user_id))
+            .await
+            .map_err(inventory_error)?;
+        if let Some(alert) = self.check_outcome(&outcome, Utc::now()).await {
+            self.alert(&alert).await;
+        }
+        Ok(outcome)
+    }
+
+    /// Run every night at `run_at_hour_utc` in the background
+    pub fn spawn(self: &Arc<Self>) -> Option<tokio::task::JoinHandle<()>> {
+        if !self.config.enabled {
+            return None;
+        }
+
+        let service = Arc::clone(self);
+        Some(tokio::spawn(async move {
+            loop {
+                let now = Utc::now();
+                let wait = (next_run(now, service.config.run_at_hour_utc) - now)
+                    .to_std()
+                    .unwrap_or_default();
+                tokio::time::sleep(wait).await;
+
+                let now = Utc::now();
+                match service.run(service.scope_for(now), now).await {
+                    Ok(run) => info!(
+                        "Reservation reconciliation checked {} items: {} drifted, {} corrected, {} refused, {} failed",
+                        run.items_checked, run.drifted, run.corrected, run.refused, run.failed
+                    ),
+                    Err(e) => warn!("Reservation reconciliation run failed: {}", e),
+                }
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use erp_master_data::inventory::reservation_drift::plan_repair;
+    use std::collections::BTreeMap;
+    use std::sync::Mutex;
+
+    type MasterDataResult<T> = erp_master_data::Result<T>;
+    type ConcurrentWriter = Box<dyn FnMut(&mut Item) + Send>;
+
+    #[derive(Debug, Clone)]
+    struct Item {
+        tenant_id: Uuid,
+        product_id: Uuid,
+        location_id: Uuid,
+        on_hand: i32,
+        /// `quantity_reserved`
+        column: i32,
+        /// Quantities of the active reservations
+        reservations: Vec<i32>,
+    }
+
+    /// Inventory items in memory; `before_lock` runs inside `repair_item`
+    /// before the item is read, as a concurrent writer would
+    #[derive(Default)]
+    struct MemoryItems {
+        items: Mutex<BTreeMap<Uuid, Item>>,
+        adjustments: Mutex<Vec<ReservationAdjustment>>,
+        before_lock: Mutex<Option<ConcurrentWriter>>,
+    }
+
+    impl MemoryItems {
+        fn add(&self, on_hand: i32, column: i32, reservations: Vec<i32>) -> Uuid {
+            let id = Uuid::new_v4();
+            let item = Item {
+                tenant_id: Uuid::from_u128(1),
+                product_id: Uuid::new_v4(),
+                location_id: Uuid::new_v4(),
+                on_hand,
+                column,
+                reservations,
+            };
+            self.items.lock().unwrap().insert(id, item);
+            id
+        }
+
+        fn measure(id: Uuid, item: &Item) -> ReservationDrift {
+            ReservationDrift {
+                tenant_id: item.tenant_id,
+                location_item_id: id,
+                product_id: item.product_id,
+                location_id: item.location_id,
+                quantity_available: item.on_hand,
+                recorded_reserved: item.column,
+                held_reserved: item.reservations.iter().sum(),
+            }
+        }
+
+        fn item(&self, id: Uuid) -> Item {
+            self.items.lock().unwrap()[&id].clone()
+        }
+    }
+
+    #[async_trait]
+    impl ReservationReconciliationRepository for MemoryItems {
+        async fn measure_items(
+            &self,
+            tenant_id: Option<Uuid>,
+            scope: ReconciliationScope,
+            after: Option<Uuid>,
+            limit: i64,
+        ) -> MasterDataResult<Vec<ReservationDrift>> {
+            let items = self.items.lock().unwrap();
+            Ok(items
+                .iter()
+                .filter(|(id, item)| {
+                    tenant_id.is_none_or(|tenant_id| item.tenant_id == tenant_id)
+                        && scope.includes(**id)
+                        && after.is_none_or(|after| **id > after)
+                })
+                .take(limit as usize)
+                .map(|(id, item)| Self::measure(*id, item))
+                .collect())
+        }
+
+        async fn repair_item(
+            &self,
+            _tenant_id: Uuid,
+            location_item_id: Uuid,
+            run_id: Uuid,
+            corrected_by: Option<Uuid>,
+        ) -> MasterDataResult<RepairOutcome> {
+            let mut items = self.items.lock().unwrap();
+            let item = items.get_mut(&location_item_id).unwrap();
+            if let Some(writer) = self.before_lock.lock().unwrap().as_mut() {
+                writer(item);
+            }
+
+            let outcome = plan_repair(&Self::measure(location_item_id, item), run_id, corrected_by, Utc::now());
+            if let RepairOutcome::Corrected { adjustment } = &outcome {
+                item.column = adjustment.corrected_reserved;
+                self.adjustments.lock().unwrap().push(adjustment.clone());
+            }
+            Ok(outcome)
+        }
+
+        async fn item_adjustments(
+            &self,
+            location_item_id: Uuid,
+            since: DateTime<Utc>,
+        ) -> MasterDataResult<Vec<ReservationAdjustment>> {
+            let adjustments = self.adjustments.lock().unwrap();
+            Ok(adjustments
+                .iter()
+                .rev()
+                .filter(|a| a.location_item_id == location_item_id && a.corrected_at > since)
+                .cloned()
+                .collect())
+        }
+
+        async fn list_adjustments(&self, tenant_id: Uuid, limit: i64) -> MasterDataResult<Vec<ReservationAdjustment>> {
+            let adjustments = self.adjustments.lock().unwrap();
+            Ok(adjustments
+                .iter()
+                .rev()
+                .filter(|a| a.tenant_id == tenant_id)
+                .take(limit as usize)
+                .cloned()
+                .collect())
+        }
+    }
+
+    fn service(items: Arc<MemoryItems>, repair: bool) -> ReservationReconciliationService {
+        let config = ReservationReconciliationConfig {
+            repair,
+            batch_size: 2,
+            batch_pause_ms: 0,
+            alert_threshold: 5,
+            alert_repeat_count: 3,
+            ..ReservationReconciliationConfig::default()
+        };
+        ReservationReconciliationService::new(items, config)
+    }
+
+    #[tokio::test]
+    async fn test_detection_finds_drift_without_touching_items() {
+        let items = Arc::new(MemoryItems::default());
+        let in_sync = items.add(50, 7, vec![3, 4]);
+        let missing = items.add(50, 2, vec![3, 4]);
+        let extra = items.add(50, 12, vec![4]);
+        let service = service(items.clone(), false);
+
+        let run = service.run(ReconciliationScope::Full, Utc::now()).await.unwrap();
+        assert_eq!((run.items_checked, run.drifted, run.corrected), (3, 2, 0));
+        assert_eq!(items.item(missing).column, 2);
+
+        let report = service.report(Uuid::from_u128(1)).await.unwrap();
+        let mut drifted: Vec<(Uuid, i32)> = report.drifted.iter().map(|d| (d.location_item_id, d.delta())).collect();
+        drifted.sort();
+        let mut expected = vec![(missing, 5), (extra, -8)];
+        expected.sort();
+        assert_eq!(drifted, expected);
+        assert!(!drifted.iter().any(|(id, _)| *id == in_sync));
+    }
+
+    #[tokio::test]
+    async fn test_repair_keeps_reservations_made_during_the_run() {
+        let items = Arc::new(MemoryItems::default());
+        let id = items.add(40, 3, vec![5, 5]);
+        // A reservation of 6 commits between detection and the lock: row and column together
+        *items.before_lock.lock().unwrap() = Some(Box::new(|item: &mut Item| {
+            item.reservations.push(6);
+            item.column += 6;
+        }));
+
+        let run = service(items.clone(), true).run(ReconciliationScope::Full, Utc::now()).await.unwrap();
+        assert_eq!((run.drifted, run.corrected), (1, 1));
+
+        let item = items.item(id);
+        assert_eq!(item.column, 16);
+        assert_eq!(item.column, item.reservations.iter().sum::<i32>());
+        // Only the drift was corrected, not the new reservation
+        let adjustments = items.adjustments.lock().unwrap();
+        assert_eq!(adjustments.len(), 1);
+        assert_eq!((adjustments[0].previous_reserved, adjustments[0].corrected_reserved), (9, 16));
+    }
+
+    #[tokio::test]
+    async fn test_repair_refuses_holds_beyond_stock_on_hand() {
+        let items = Arc::new(MemoryItems::default());
+        let id = items.add(5, 2, vec![4, 4]);
+
+        let run = service(items.clone(), true).run(ReconciliationScope::Full, Utc::now()).await.unwrap();
+        assert_eq!((run.corrected, run.refused), (0, 1));
+        assert_eq!(items.item(id).column, 2);
+        assert_eq!(run.alerts.len(), 1);
+        assert!(items.adjustments.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_drift_coming_back_raises_alert() {
+        let items = Arc::new(MemoryItems::default());
+        let id = items.add(100, 0, vec![20]);
+        let service = service(items.clone(), true);
+
+        for night in 1..=3 {
+            let run = service.run(ReconciliationScope::Full, Utc::now()).await.unwrap();
+            assert_eq!(run.corrected, 1);
+            assert_eq!(run.alerts.len(), usize::from(night == 3), "night {}", night);
+            // The broken code path loses the reservation again
+            items.items.lock().unwrap().get_mut(&id).unwrap().column = 0;
+        }
+    }
+}
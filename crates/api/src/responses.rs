@@ -0,0 +1,287 @@
+//! Versioned response DTOs
+//!
+//! Response shapes that differ between API versions (see
+//! [`crate::api_middleware::api_version`]) are modelled as explicit structs per
+//! version with converters from the domain models. Handlers pick the struct
+//! matching the request's [`ApiVersion`]; a value that fails to serialize is
+//! answered with 500 rather than a `null` in its place.
+//!
+//! - **v1** keeps the original shapes: customers are the serialized domain
+//!   model, product prices are integer cents next to a `currency` field.
+//! - **v2** uses curated DTOs where every monetary value is a [`Money`] object.
+
+use chrono::{DateTime, Utc};
+use erp_master_data::customer::model::{Customer, CustomerLifecycleStage, CustomerType, CreditStatus};
+use erp_master_data::customer::CustomerAssignment;
+use erp_master_data::product::{Product, ProductSummary};
+use erp_master_data::types::EntityStatus;
+use axum::http::StatusCode;
+use serde::Serialize;
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::api_middleware::api_version::ApiVersion;
+
+/// Monetary amount with its currency; the amount is a decimal string to avoid float rounding
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Money {
+    pub amount: String,
+    pub currency: String,
+}
+
+impl Money {
+    pub fn from_cents(cents: i64, currency: &str) -> Self {
+        let sign = if cents < 0 { "-" } else { "" };
+        let abs = cents.unsigned_abs();
+        Self {
+            amount: format!("{}{}.{:02}", sign, abs / 100, abs % 100),
+            currency: currency.to_string(),
+        }
+    }
+}
+
+/// v1 customer: the domain model as it has always been returned
+#[derive(Debug, Serialize)]
+#[serde(transparent)]
+pub struct CustomerResponseV1(pub Customer);
+
+impl From<Customer> for CustomerResponseV1 {
+    fn from(customer: Customer) -> Self {
+        Self(customer)
+    }
+}
+
+/// v2 customer: stable public fields only, credit limit as [`Money`]
+#[derive(Debug, Serialize)]
+pub struct CustomerResponseV2 {
+    pub id: Uuid,
+    pub customer_number: String,
+    pub legal_name: String,
+    pub trade_names: Vec<String>,
+    pub customer_type: CustomerType,
+    pub parent_customer_id: Option<Uuid>,
+    pub corporate_group_id: Option<Uuid>,
+    pub lifecycle_stage: CustomerLifecycleStage,
+    pub status: EntityStatus,
+    pub credit_status: CreditStatus,
+    pub currency: String,
+    pub credit_limit: Option<Money>,
+    pub tax_exempt: bool,
+    pub sales_representative_id: Option<Uuid>,
+    pub account_manager_id: Option<Uuid>,
+    /// Derived and overridden assignees with where the effective ones come from
+    pub assignment: CustomerAssignment,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub version: i32,
+}
+
+impl From<Customer> for CustomerResponseV2 {
+    fn from(customer: Customer) -> Self {
+        let currency = customer.financial_info.currency_code;
+        let credit_limit = customer.financial_info.credit_limit.map(|limit| Money {
+            amount: limit.to_string(),
+            currency: currency.clone(),
+        });
+
+        Self {
+            id: customer.id,
+            customer_number: customer.customer_number,
+            legal_name: customer.legal_name,
+            trade_names: customer.trade_names,
+            customer_type: customer.customer_type,
+            parent_customer_id: customer.parent_customer_id,
+            corporate_group_id: customer.corporate_group_id,
+            lifecycle_stage: customer.lifecycle_stage,
+            status: customer.status,
+            credit_status: customer.credit_status,
+            currency,
+            credit_limit,
+            tax_exempt: customer.financial_info.tax_exempt,
+            sales_representative_id: customer.sales_representative_id,
+            account_manager_id: customer.account_manager_id,
+            assignment: customer.assignment,
+            created_at: customer.audit.created_at,
+            updated_at: customer.audit.modified_at,
+            version: customer.audit.version,
+        }
+    }
+}
+
+fn serialization_failed(e: serde_json::Error) -> StatusCode {
+    tracing::error!("Failed to serialize response: {}", e);
+    StatusCode::INTERNAL_SERVER_ERROR
+}
+
+/// Serialize a customer in the shape of the requested version
+pub fn customer_response(version: ApiVersion, customer: Customer) -> Result<Value, StatusCode> {
+    let value = match version {
+        ApiVersion::V1 => serde_json::to_value(CustomerResponseV1::from(customer)),
+        ApiVersion::V2 => serde_json::to_value(CustomerResponseV2::from(customer)),
+    };
+    value.map_err(serialization_failed)
+}
+
+/// v1 product price fields: integer cents
+#[derive(Debug, Serialize)]
+pub struct ProductPricesV1 {
+    pub base_price: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cost_price: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub list_price: Option<i64>,
+    pub currency: String,
+}
+
+impl From<&Product> for ProductPricesV1 {
+    fn from(product: &Product) -> Self {
+        Self {
+            base_price: product.base_price,
+            cost_price: product.cost_price,
+            list_price: product.list_price,
+            currency: product.currency.clone(),
+        }
+    }
+}
+
+impl From<&ProductSummary> for ProductPricesV1 {
+    fn from(product: &ProductSummary) -> Self {
+        Self {
+            base_price: product.base_price,
+            cost_price: None,
+            list_price: None,
+            currency: product.currency.clone(),
+        }
+    }
+}
+
+/// v2 product price fields: [`Money`] objects
+#[derive(Debug, Serialize)]
+pub struct ProductPricesV2 {
+    pub base_price: Money,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cost_price: Option<Money>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub list_price: Option<Money>,
+}
+
+impl From<&Product> for ProductPricesV2 {
+    fn from(product: &Product) -> Self {
+        let money = |cents: i64| Money::from_cents(cents, &product.currency);
+        Self {
+            base_price: money(product.base_price),
+            cost_price: product.cost_price.map(money),
+            list_price: product.list_price.map(money),
+        }
+    }
+}
+
+impl From<&ProductSummary> for ProductPricesV2 {
+    fn from(product: &ProductSummary) -> Self {
+        Self {
+            base_price: Money::from_cents(product.base_price, &product.currency),
+            cost_price: None,
+            list_price: None,
+        }
+    }
+}
+
+/// Price fields of a product or product summary in the shape of the requested version
+pub fn product_prices<P>(version: ApiVersion, product: &P) -> Result<Value, StatusCode>
+where
+    for<'a> ProductPricesV1: From<&'a P>,
+    for<'a> ProductPricesV2: From<&'a P>,
+{
+    let value = match version {
+        ApiVersion::V1 => serde_json::to_value(ProductPricesV1::from(product)),
+        ApiVersion::V2 => serde_json::to_value(ProductPricesV2::from(product)),
+    };
+    value.map_err(serialization_failed)
+}
+
+/// A product or product summary as serialized, with its price fields in the
+/// shape of the requested version
+pub fn product_response<P>(version: ApiVersion, product: &P) -> Result<Value, StatusCode>
+where
+    P: Serialize,
+    for<'a> ProductPricesV1: From<&'a P>,
+    for<'a> ProductPricesV2: From<&'a P>,
+{
+    let mut value = serde_json::to_value(product).map_err(serialization_failed)?;
+    if let (Value::Object(fields), Value::Object(prices)) = (&mut value, product_prices(version, product)?) {
+        fields.extend(prices);
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn customer() -> Customer {
+        let mut customer = Customer {
+            customer_number: "CUST-0001".to_string(),
+            legal_name: "Acme GmbH".to_string(),
+            ..Default::default()
+        };
+        customer.financial_info.currency_code = "EUR".to_string();
+        customer.financial_info.credit_limit = Some("15000.50".parse().unwrap());
+        customer
+    }
+
+    #[test]
+    fn test_customer_v1_is_domain_model() {
+        let customer = customer();
+        let expected = serde_json::to_value(&customer).unwrap();
+
+        let body = customer_response(ApiVersion::V1, customer).unwrap();
+        assert_eq!(body, expected);
+        assert_eq!(body["financial_info"]["currency_code"], "EUR");
+        assert!(body.get("credit_limit").is_none());
+    }
+
+    #[test]
+    fn test_customer_v2_uses_money() {
+        let customer = customer();
+        let id = customer.id;
+
+        let body = customer_response(ApiVersion::V2, customer).unwrap();
+        assert_eq!(body["id"], json!(id));
+        assert_eq!(body["legal_name"], "Acme GmbH");
+        assert_eq!(body["credit_limit"], json!({"amount": "15000.50", "currency": "EUR"}));
+        assert!(body.get("financial_info").is_none());
+        assert!(body.get("audit").is_none());
+    }
+
+    #[test]
+    fn test_product_prices_per_version() {
+        let mut product = Product::new(Uuid::new_v4(), "SKU-1".to_string(), "Widget".to_string(), Uuid::new_v4());
+        product.base_price = 1999;
+        product.cost_price = Some(-5);
+        product.currency = "USD".to_string();
+
+        let v1 = product_prices(ApiVersion::V1, &product).unwrap();
+        assert_eq!(v1["base_price"], 1999);
+        assert_eq!(v1["currency"], "USD");
+
+        let v2 = product_prices(ApiVersion::V2, &product).unwrap();
+        assert_eq!(v2["base_price"], json!({"amount": "19.99", "currency": "USD"}));
+        assert_eq!(v2["cost_price"], json!({"amount": "-0.05", "currency": "USD"}));
+    }
+
+    #[test]
+    fn test_product_response_replaces_price_fields() {
+        let mut product = Product::new(Uuid::new_v4(), "SKU-1".to_string(), "Widget".to_string(), Uuid::new_v4());
+        product.base_price = 1999;
+        product.currency = "EUR".to_string();
+
+        let v1 = product_response(ApiVersion::V1, &product).unwrap();
+        assert_eq!(v1, serde_json::to_value(&product).unwrap());
+
+        let v2 = product_response(ApiVersion::V2, &product).unwrap();
+        assert_eq!(v2["sku"], "SKU-1");
+        assert_eq!(v2["base_price"], json!({"amount": "19.99", "currency": "EUR"}));
+        assert!(v2["cost_price"].is_null());
+    }
+}
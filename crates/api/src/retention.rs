@@ -0,0 +1,625 @@
+//! # Retention Enforcement
+//!
+//! Runs the retention policies of every category registered in the
+//! [`RetentionRegistry`] for every active tenant, once a night at
+//! `retention.run_at_hour_utc`. Policies live in `public.retention_policies`
+//! (a row without a tenant is the global default) and are edited through the
+//! admin endpoints under `/api/v1/admin/retention`.
+//!
+//! Each policy runs in batches of `retention.batch_size` rows and stops after
+//! `retention.max_batches_per_policy`; whatever is left is picked up the next
+//! night. Every run is recorded in `public.retention_enforcement_log`.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use erp_core::retention::{
+    validate_policy, EffectivePolicy, PolicySource, RetentionAction, RetentionPolicy, RetentionRegistry,
+};
+use erp_core::{Error, ErrorCode, Result, RetentionConfig};
+use serde::Serialize;
+use sqlx::{PgPool, Row};
+use std::sync::Arc;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// One policy run as stored in `retention_enforcement_log`
+#[derive(Debug, Clone, Serialize)]
+pub struct RetentionEnforcement {
+    pub id: Uuid,
+    pub tenant_id: Uuid,
+    pub category: String,
+    pub action: RetentionAction,
+    pub retention_days: i64,
+    pub policy_source: PolicySource,
+    pub cutoff: DateTime<Utc>,
+    pub rows_affected: u64,
+    pub batches: u32,
+    /// False when the run stopped at the batch limit or failed
+    pub completed: bool,
+    pub error: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+}
+
+/// Rows a policy would touch if it ran now
+#[derive(Debug, Clone, Serialize)]
+pub struct RetentionDryRun {
+    pub policy: EffectivePolicy,
+    pub cutoff: DateTime<Utc>,
+    pub affected_rows: u64,
+}
+
+/// Policies, tenants and the enforcement log
+#[async_trait]
+pub trait RetentionStore: Send + Sync {
+    async fn active_tenants(&self) -> Result<Vec<Uuid>>;
+
+    /// Global policies plus, when given, the tenant's own
+    async fn policies(&self, tenant_id: Option<Uuid>) -> Result<Vec<RetentionPolicy>>;
+
+    /// Insert or replace the policy for its tenant (or globally) and category
+    async fn save_policy(&self, policy: &RetentionPolicy) -> Result<()>;
+
+    async fn record_enforcement(&self, entry: &RetentionEnforcement) -> Result<()>;
+
+    /// Most recent runs first
+    async fn enforcement_log(&self, tenant_id: Option<Uuid>, limit: i64) -> Result<Vec<RetentionEnforcement>>;
+}
+
+pub struct PostgresRetentionStore {
+    pool: PgPool,
+}
+
+impl PostgresRetentionStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+fn corrupt(what: &str, value: &str) -> Error {
+    Error::new(ErrorCode::DatabaseError, format!("Unknown retention {} '{}'", what, value))
+}
+
+fn parse_action(value: &str) -> Result<RetentionAction> {
+    RetentionAction::parse(value).ok_or_else(|| corrupt("action", value))
+}
+
+fn parse_source(value: &str) -> Result<PolicySource> {
+    match value {
+        "tenant" => Ok(PolicySource::Tenant),
+        "global" => Ok(PolicySource::Global),
+        "builtin" => Ok(PolicySource::Builtin),
+        _ => Err(corrupt("policy source", value)),
+    }
+}
+
+fn source_str(source: PolicySource) -> &'static str {
+    match source {
+        PolicySource::Tenant => "tenant",
+        PolicySource::Global => "global",
+        PolicySource::Builtin => "builtin",
+    }
+}
+
+#[async_trait]
+impl RetentionStore for PostgresRetentionStore {
+    async fn active_tenants(&self) -> Result<Vec<Uuid>> {
+        let ids = sqlx::query_scalar("SELECT id FROM public.tenants WHERE status = 'active' ORDER BY id")
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(ids)
+    }
+
+    async fn policies(&self, tenant_id: Option<Uuid>) -> Result<Vec<RetentionPolicy>> {
+        let rows = sqlx::query(
+            "SELECT tenant_id, category, retention_days, action, updated_by, updated_at \
+             FROM public.retention_policies WHERE tenant_id IS NULL OR tenant_id = $1 \
+             ORDER BY category, tenant_id NULLS FIRST",
+        )
+        .bind(tenant_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                Ok(RetentionPolicy {
+                    tenant_id: row.get("tenant_id"),
+                    category: row.get("category"),
+                    retention_days: row.get::<i32, _>("retention_days") as i64,
+                    action: parse_action(row.get("action"))?,
+                    updated_by: row.get("updated_by"),
+                    updated_at: row.get("updated_at"),
+                })
+            })
+            .collect()
+    }
+
+    async fn save_policy(&self, policy: &RetentionPolicy) -> Result<()> {
+        // NULL tenants never conflict, so global rows are replaced explicitly
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(
+            "DELETE FROM public.retention_policies WHERE tenant_id IS NOT DISTINCT FROM $1 AND category = $2",
+        )
+        .bind(policy.tenant_id)
+        .bind(&policy.category)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(
+            "INSERT INTO public.retention_policies \
+             (tenant_id, category, retention_days, action, updated_by, updated_at) \
+             VALUES ($1, $2, $3, $4, $5, $6)",
+        )
+        .bind(policy.tenant_id)
+        .bind(&policy.category)
+        .bind(policy.retention_days as i32)
+        .bind(policy.action.as_str())
+        .bind(policy.updated_by)
+        .bind(policy.updated_at)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn record_enforcement(&self, entry: &RetentionEnforcement) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO public.retention_enforcement_log (
+                id, tenant_id, category, action, retention_days, policy_source, cutoff,
+                rows_affected, batches, completed, error, started_at, finished_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+            "#,
+        )
+        .bind(entry.id)
+        .bind(entry.tenant_id)
+        .bind(&entry.category)
+        .bind(entry.action.as_str())
+        .bind(entry.retention_days as i32)
+        .bind(source_str(entry.policy_source))
+        .bind(entry.cutoff)
+        .bind(entry.rows_affected as i64)
+        .bind(entry.batches as i32)
+        .bind(entry.completed)
+        .bind(&entry.error)
+        .bind(entry.started_at)
+        .bind(entry.finished_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn enforcement_log(&self, tenant_id: Option<Uuid>, limit: i64) -> Result<Vec<RetentionEnforcement>> {
+        let rows = sqlx::query(
+            "SELECT id, tenant_id, category, action, retention_days, policy_source, cutoff, \
+             rows_affected, batches, completed, error, started_at, finished_at \
+             FROM public.retention_enforcement_log WHERE $1::UUID IS NULL OR tenant_id = $1 \
+             ORDER BY started_at DESC LIMIT $2",
+        )
+        .bind(tenant_id)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                Ok(RetentionEnforcement {
+                    id: row.get("id"),
+                    tenant_id: row.get("tenant_id"),
+                    category: row.get("category"),
+                    action: parse_action(row.get("action"))?,
+                    retention_days: row.get::<i32, _>("retention_days") as i64,
+                    policy_source: parse_source(row.get("policy_source"))?,
+                    cutoff: row.get("cutoff"),
+                    rows_affected: row.get::<i64, _>("rows_affected") as u64,
+                    batches: row.get::<i32, _>("batches") as u32,
+                    completed: row.get("completed"),
+                    error: row.get("error"),
+                    started_at: row.get("started_at"),
+                    finished_at: row.get("finished_at"),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Policy administration and the nightly enforcement run
+pub struct RetentionService {
+    registry: RetentionRegistry,
+    store: Arc<dyn RetentionStore>,
+    config: RetentionConfig,
+}
+
+impl RetentionService {
+    pub fn new(registry: RetentionRegistry, store: Arc<dyn RetentionStore>, config: RetentionConfig) -> Self {
+        Self { registry, store, config }
+    }
+
+    pub fn registry(&self) -> &RetentionRegistry {
+        &self.registry
+    }
+
+    pub fn config(&self) -> &RetentionConfig {
+        &self.config
+    }
+
+    pub fn store(&self) -> &Arc<dyn RetentionStore> {
+        &self.store
+    }
+
+    /// Policy of every category as it applies to the tenant
+    pub async fn effective_policies(&self, tenant_id: Uuid) -> Result<Vec<EffectivePolicy>> {
+        let policies = self.store.policies(Some(tenant_id)).await?;
+        Ok(self.registry.resolve(tenant_id, &policies))
+    }
+
+    /// Validate against the legal minimums and store
+    pub async fn update_policy(&self, policy: RetentionPolicy) -> Result<RetentionPolicy> {
+        validate_policy(&self.registry, &policy, &self.config.legal_minimum_days)?;
+        self.store.save_policy(&policy).await?;
+        Ok(policy)
+    }
+
+    pub async fn dry_run(&self, tenant_id: Uuid, category: &str, now: DateTime<Utc>) -> Result<RetentionDryRun> {
+        let Some(entry) = self.registry.get(category) else {
+            return Err(Error::new(
+                ErrorCode::ResourceNotFound,
+                format!("Unknown retention category '{}'", category),
+            ));
+        };
+        let policies = self.store.policies(Some(tenant_id)).await?;
+        let policy = erp_core::retention::resolve_policy(entry, tenant_id, &policies);
+        let cutoff = policy.cutoff(now);
+        let affected_rows = entry.handler.count_expired(tenant_id, cutoff, policy.action).await?;
+
+        Ok(RetentionDryRun {
+            policy,
+            cutoff,
+            affected_rows,
+        })
+    }
+
+    /// Run one policy in bounded batches; failures end up in the log entry
+    async fn enforce_policy(&self, policy: &EffectivePolicy, now: DateTime<Utc>) -> RetentionEnforcement {
+        let started_at = Utc::now();
+        let cutoff = policy.cutoff(now);
+        let batch_size = self.config.batch_size.max(1);
+        let mut entry = RetentionEnforcement {
+            id: Uuid::new_v4(),
+            tenant_id: policy.tenant_id,
+            category: policy.category.clone(),
+            action: policy.action,
+            retention_days: policy.retention_days,
+            policy_source: policy.source,
+            cutoff,
+            rows_affected: 0,
+            batches: 0,
+            completed: false,
+            error: None,
+            started_at,
+            finished_at: started_at,
+        };
+
+        if let Some(category) = self.registry.get(&policy.category) {
+            while entry.batches < self.config.max_batches_per_policy {
+                match category
+                    .handler
+                    .enforce_batch(policy.tenant_id, cutoff, policy.action, batch_size)
+                    .await
+                {
+                    Ok(affected) => {
+                        entry.batches += 1;
+                        entry.rows_affected += affected;
+                        if (affected as i64) < batch_size {
+                            entry.completed = true;
+                            break;
+                        }
+                    }
+                    Err(e) => {
+                        entry.error = Some(e.to_string());
+                        break;
+                    }
+                }
+            }
+        }
+
+        entry.finished_at = Utc::now();
+        entry
+    }
+
+    /// Enforce every category for one tenant and log each run
+    pub async fn enforce_tenant(&self, tenant_id: Uuid, now: DateTime<Utc>) -> Result<Vec<RetentionEnforcement>> {
+        let mut entries = Vec::new();
+        for policy in self.effective_policies(tenant_id).await? {
+            let entry = self.enforce_policy(&policy, now).await;
+            if let Err(e) = self.store.record_enforcement(&entry).await {
+                warn!("Failed to log retention run for {} of tenant {}: {}", entry.category, tenant_id, e);
+            }
+            entries.push(entry);
+        }
+        Ok(entries)
+    }
+
+    /// Enforce all policies of all active tenants; returns rows affected
+    pub async fn enforce_all(&self, now: DateTime<Utc>) -> Result<u64> {
+        let mut affected = 0;
+        for tenant_id in self.store.active_tenants().await? {
+            match self.enforce_tenant(tenant_id, now).await {
+                Ok(entries) => affected += entries.iter().map(|e| e.rows_affected).sum::<u64>(),
+                Err(e) => warn!("Retention enforcement failed for tenant {}: {}", tenant_id, e),
+            }
+        }
+        Ok(affected)
+    }
+
+    /// Run enforcement nightly at `retention.run_at_hour_utc` in the background
+    pub fn spawn(self: &Arc<Self>) -> Option<tokio::task::JoinHandle<()>> {
+        if !self.config.enabled {
+            return None;
+        }
+
+        let service = Arc::clone(self);
+        Some(tokio::spawn(async move {
+            loop {
+                let now = Utc::now();
+                let wait = (next_run(now, service.config.run_at_hour_utc) - now)
+                    .to_std()
+                    .unwrap_or_default();
+                tokio::time::sleep(wait).await;
+
+                match service.enforce_all(Utc::now()).await {
+                    Ok(affected) => info!("Retention enforcement affected {} rows", affected),
+                    Err(e) => warn!("Retention enforcement run failed: {}", e),
+                }
+            }
+        }))
+    }
+}
+
+/// Next time after `now` at the start of `hour` UTC
+pub(crate) fn next_run(now: DateTime<Utc>, hour: u32) -> DateTime<Utc> {
+    let today = now
+        .date_naive()
+        .and_hms_opt(hour.min(23), 0, 0)
+        .expect("valid hour")
+        .and_utc();
+    if today > now {
+        today
+    } else {
+        today + Duration::days(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use erp_core::retention::{RetentionCategory, RetentionHandler};
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    #[derive(Debug, Clone)]
+    struct Note {
+        tenant_id: Uuid,
+        occurred_at: DateTime<Utc>,
+        body: Option<String>,
+        anonymized: bool,
+    }
+
+    /// Communication-like rows that can be deleted or anonymized
+    #[derive(Default)]
+    struct MemoryNotes {
+        rows: Mutex<Vec<Note>>,
+    }
+
+    impl MemoryNotes {
+        fn add(&self, tenant_id: Uuid, days_old: i64, now: DateTime<Utc>) {
+            self.rows.lock().unwrap().push(Note {
+                tenant_id,
+                occurred_at: now - Duration::days(days_old),
+                body: Some("Called about invoice".to_string()),
+                anonymized: false,
+            });
+        }
+
+        fn expired(note: &Note, tenant_id: Uuid, cutoff: DateTime<Utc>, action: RetentionAction) -> bool {
+            note.tenant_id == tenant_id
+                && note.occurred_at < cutoff
+                && (action == RetentionAction::Delete || !note.anonymized)
+        }
+    }
+
+    #[async_trait]
+    impl RetentionHandler for MemoryNotes {
+        async fn count_expired(&self, tenant_id: Uuid, cutoff: DateTime<Utc>, action: RetentionAction) -> Result<u64> {
+            let rows = self.rows.lock().unwrap();
+            Ok(rows.iter().filter(|n| Self::expired(n, tenant_id, cutoff, action)).count() as u64)
+        }
+
+        async fn enforce_batch(
+            &self,
+            tenant_id: Uuid,
+            cutoff: DateTime<Utc>,
+            action: RetentionAction,
+            limit: i64,
+        ) -> Result<u64> {
+            let mut rows = self.rows.lock().unwrap();
+            let mut affected = 0;
+            match action {
+                RetentionAction::Delete => rows.retain(|n| {
+                    let remove = (affected as i64) < limit && Self::expired(n, tenant_id, cutoff, action);
+                    affected += remove as u64;
+                    !remove
+                }),
+                RetentionAction::Anonymize => {
+                    for note in rows.iter_mut() {
+                        if (affected as i64) < limit && Self::expired(note, tenant_id, cutoff, action) {
+                            note.body = None;
+                            note.anonymized = true;
+                            affected += 1;
+                        }
+                    }
+                }
+                RetentionAction::Archive => unreachable!("not registered"),
+            }
+            Ok(affected)
+        }
+    }
+
+    #[derive(Default)]
+    struct MemoryStore {
+        tenants: Vec<Uuid>,
+        policies: Mutex<Vec<RetentionPolicy>>,
+        log: Mutex<Vec<RetentionEnforcement>>,
+    }
+
+    #[async_trait]
+    impl RetentionStore for MemoryStore {
+        async fn active_tenants(&self) -> Result<Vec<Uuid>> {
+            Ok(self.tenants.clone())
+        }
+
+        async fn policies(&self, tenant_id: Option<Uuid>) -> Result<Vec<RetentionPolicy>> {
+            let policies = self.policies.lock().unwrap();
+            Ok(policies
+                .iter()
+                .filter(|p| p.tenant_id.is_none() || p.tenant_id == tenant_id)
+                .cloned()
+                .collect())
+        }
+
+        async fn save_policy(&self, policy: &RetentionPolicy) -> Result<()> {
+            let mut policies = self.policies.lock().unwrap();
+            policies.retain(|p| !(p.tenant_id == policy.tenant_id && p.category == policy.category));
+            policies.push(policy.clone());
+            Ok(())
+        }
+
+        async fn record_enforcement(&self, entry: &RetentionEnforcement) -> Result<()> {
+            self.log.lock().unwrap().push(entry.clone());
+            Ok(())
+        }
+
+        async fn enforcement_log(&self, _tenant_id: Option<Uuid>, _limit: i64) -> Result<Vec<RetentionEnforcement>> {
+            Ok(self.log.lock().unwrap().iter().rev().cloned().collect())
+        }
+    }
+
+    fn service(notes: Arc<MemoryNotes>, store: Arc<MemoryStore>, batch_size: i64, max_batches: u32) -> RetentionService {
+        let mut registry = RetentionRegistry::new();
+        registry.register(RetentionCategory {
+            name: "customer_communications".to_string(),
+            description: "Customer communication log".to_string(),
+            default_days: 1095,
+            default_action: RetentionAction::Delete,
+            supported_actions: vec![RetentionAction::Delete, RetentionAction::Anonymize],
+            handler: notes,
+        });
+        let config = RetentionConfig {
+            batch_size,
+            max_batches_per_policy: max_batches,
+            legal_minimum_days: HashMap::from([("customer_communications".to_string(), 30)]),
+            ..Default::default()
+        };
+        RetentionService::new(registry, store, config)
+    }
+
+    fn policy(tenant_id: Option<Uuid>, days: i64, action: RetentionAction) -> RetentionPolicy {
+        RetentionPolicy {
+            tenant_id,
+            category: "customer_communications".to_string(),
+            retention_days: days,
+            action,
+            updated_by: Some(Uuid::new_v4()),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tenant_override_and_global_default_are_enforced() {
+        let now = Utc::now();
+        let (strict, relaxed) = (Uuid::new_v4(), Uuid::new_v4());
+        let notes = Arc::new(MemoryNotes::default());
+        for tenant in [strict, relaxed] {
+            notes.add(tenant, 400, now);
+            notes.add(tenant, 100, now);
+            notes.add(tenant, 10, now);
+        }
+        let store = Arc::new(MemoryStore { tenants: vec![strict, relaxed], ..Default::default() });
+        let service = service(notes.clone(), store, 100, 10);
+
+        service.update_policy(policy(None, 365, RetentionAction::Delete)).await.unwrap();
+        service.update_policy(policy(Some(strict), 90, RetentionAction::Delete)).await.unwrap();
+        assert!(service.update_policy(policy(Some(strict), 7, RetentionAction::Delete)).await.is_err());
+
+        let dry_run = service.dry_run(strict, "customer_communications", now).await.unwrap();
+        assert_eq!((dry_run.affected_rows, dry_run.policy.source), (2, PolicySource::Tenant));
+        let dry_run = service.dry_run(relaxed, "customer_communications", now).await.unwrap();
+        assert_eq!((dry_run.affected_rows, dry_run.policy.source), (1, PolicySource::Global));
+        assert_eq!(notes.rows.lock().unwrap().len(), 6, "dry run must not change data");
+
+        assert_eq!(service.enforce_all(now).await.unwrap(), 3);
+        let remaining = |tenant| notes.rows.lock().unwrap().iter().filter(|n| n.tenant_id == tenant).count();
+        assert_eq!(remaining(strict), 1);
+        assert_eq!(remaining(relaxed), 2);
+    }
+
+    #[tokio::test]
+    async fn test_anonymize_keeps_rows_and_strips_content() {
+        let now = Utc::now();
+        let tenant = Uuid::new_v4();
+        let notes = Arc::new(MemoryNotes::default());
+        notes.add(tenant, 200, now);
+        notes.add(tenant, 5, now);
+        let store = Arc::new(MemoryStore { tenants: vec![tenant], ..Default::default() });
+        let service = service(notes.clone(), store, 100, 10);
+        service.update_policy(policy(Some(tenant), 180, RetentionAction::Anonymize)).await.unwrap();
+
+        let entries = service.enforce_tenant(tenant, now).await.unwrap();
+        assert_eq!(entries[0].action, RetentionAction::Anonymize);
+        assert_eq!(entries[0].rows_affected, 1);
+
+        let rows = notes.rows.lock().unwrap().clone();
+        assert_eq!(rows.len(), 2);
+        assert!(rows[0].anonymized && rows[0].body.is_none());
+        assert_eq!(rows[1].body.as_deref(), Some("Called about invoice"));
+        drop(rows);
+
+        // Already anonymized rows are not touched again
+        let dry_run = service.dry_run(tenant, "customer_communications", now).await.unwrap();
+        assert_eq!(dry_run.affected_rows, 0);
+    }
+
+    #[tokio::test]
+    async fn test_enforcement_log_records_bounded_batches() {
+        let now = Utc::now();
+        let tenant = Uuid::new_v4();
+        let notes = Arc::new(MemoryNotes::default());
+        for _ in 0..7 {
+            notes.add(tenant, 2000, now);
+        }
+        let store = Arc::new(MemoryStore { tenants: vec![tenant], ..Default::default() });
+        let service = service(notes.clone(), store.clone(), 2, 3);
+
+        service.enforce_all(now).await.unwrap();
+        service.enforce_all(now).await.unwrap();
+
+        let log = store.enforcement_log(None, 10).await.unwrap();
+        assert_eq!(log.len(), 2);
+        let (second, first) = (&log[0], &log[1]);
+        assert_eq!(first.category, "customer_communications");
+        assert_eq!(first.policy_source, PolicySource::Builtin);
+        assert_eq!(first.cutoff, now - Duration::days(1095));
+        assert_eq!((first.batches, first.rows_affected, first.completed), (3, 6, false));
+        assert_eq!((second.batches, second.rows_affected, second.completed), (1, 1, true));
+        assert!(first.error.is_none());
+        assert!(notes.rows.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_next_run_is_tonight_or_tomorrow() {
+        let evening = "2026-03-10T22:15:00Z".parse::<DateTime<Utc>>().unwrap();
+        assert_eq!(next_run(evening, 2), "2026-03-11T02:00:00Z".parse::<DateTime<Utc>>().unwrap());
+        let night = "2026-03-10T01:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        assert_eq!(next_run(night, 2), "2026-03-10T02:00:00Z".parse::<DateTime<Utc>>().unwrap());
+    }
+}
@@ -0,0 +1,443 @@
+//! # Sandbox Tenant Reset
+//!
+//! Sandbox tenants (`tenants.sandbox`) hold test data for integrators and are
+//! restored every night at `sandbox.reset_hour_utc` to their golden snapshot
+//! (see [`erp_core::sandbox`]). A reset replaces the data of every table of
+//! the tenant schema and the tenant's settings, which carry its feature flags.
+//! Sessions and tokens of the tenant are revoked only after a successful
+//! restore; a failed reset leaves the data and logins as they were.
+//!
+//! Every reset is stored in `public.sandbox_resets` and, with
+//! `sandbox.webhook_url` set, posted as a `sandbox.reset` event so
+//! integrators know to log in again and reload their fixtures.
+//!
+//! Responses to requests for a sandbox tenant carry `X-Sandbox: true` (see
+//! [`crate::api_middleware::sandbox`]). Sandbox data is left out of
+//! `erp-deploy backup create`; no billing or usage alerting exists yet, and
+//! whichever is added has to skip sandbox tenants the same way.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use erp_auth::AuthService;
+use erp_core::outbound::OutboundClient;
+use erp_core::sandbox::{restore_snapshot, RestoreSummary};
+use erp_core::{Error, ErrorCode, Result, SandboxConfig};
+use serde::Serialize;
+use sqlx::PgPool;
+use std::collections::HashSet;
+use std::sync::{Arc, RwLock};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::retention::next_run;
+
+/// Event type posted to `sandbox.webhook_url`
+pub const SANDBOX_RESET_EVENT: &str = "sandbox.reset";
+
+/// Outbound integration the reset webhook is sent through
+pub const SANDBOX_WEBHOOK_INTEGRATION: &str = "sandbox_webhooks";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResetStatus {
+    Succeeded,
+    Failed,
+}
+
+impl ResetStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ResetStatus::Succeeded => "succeeded",
+            ResetStatus::Failed => "failed",
+        }
+    }
+}
+
+/// One reset of a sandbox tenant
+#[derive(Debug, Clone, Serialize)]
+pub struct SandboxReset {
+    pub id: Uuid,
+    pub tenant_id: Uuid,
+    /// `None` when the restore failed before the snapshot was found
+    pub snapshot_id: Option<Uuid>,
+    pub status: ResetStatus,
+    pub tables_restored: usize,
+    pub rows_restored: i64,
+    pub sessions_revoked: u32,
+    pub error: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+}
+
+#[async_trait]
+pub trait SandboxStore: Send + Sync {
+    /// Active sandbox tenants
+    async fn sandbox_tenants(&self) -> Result<Vec<Uuid>>;
+
+    /// Restore the tenant to its golden snapshot, all or nothing
+    async fn restore(&self, tenant_id: Uuid) -> Result<RestoreSummary>;
+
+    async fn record_reset(&self, reset: &SandboxReset) -> Result<()>;
+}
+
+/// Ends every session of a tenant once its data was reset
+#[async_trait]
+pub trait SessionInvalidator: Send + Sync {
+    /// Returns the number of sessions ended
+    async fn revoke_tenant_sessions(&self, tenant_id: Uuid, reason: &str) -> Result<u32>;
+}
+
+#[async_trait]
+impl SessionInvalidator for AuthService {
+    async fn revoke_tenant_sessions(&self, tenant_id: Uuid, reason: &str) -> Result<u32> {
+        AuthService::revoke_tenant_sessions(self, tenant_id, reason).await
+    }
+}
+
+/// Tells integrators a sandbox was reset
+#[async_trait]
+pub trait ResetAnnouncer: Send + Sync {
+    async fn announce(&self, reset: &SandboxReset) -> Result<()>;
+}
+
+/// Posts a `sandbox.reset` event to `sandbox.webhook_url`
+pub struct WebhookAnnouncer {
+    client: OutboundClient,
+    url: String,
+}
+
+impl WebhookAnnouncer {
+    pub fn new(client: OutboundClient, url: String) -> Self {
+        Self { client, url }
+    }
+}
+
+#[async_trait]
+impl ResetAnnouncer for WebhookAnnouncer {
+    async fn announce(&self, reset: &SandboxReset) -> Result<()> {
+        let event = serde_json::json!({
+            "event": SANDBOX_RESET_EVENT,
+            "tenant_id": reset.tenant_id,
+            "snapshot_id": reset.snapshot_id,
+            "status": reset.status,
+            "tables_restored": reset.tables_restored,
+            "rows_restored": reset.rows_restored,
+            "reset_at": reset.finished_at,
+        });
+        let response = self.client.send(self.client.post(&self.url).json(&event)).await?;
+        if !response.status().is_success() {
+            return Err(Error::new(
+                ErrorCode::ExternalServiceError,
+                format!("Sandbox webhook answered {}", response.status()),
+            ));
+        }
+        Ok(())
+    }
+}
+
+pub struct PostgresSandboxStore {
+    pool: PgPool,
+}
+
+impl PostgresSandboxStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl SandboxStore for PostgresSandboxStore {
+    async fn sandbox_tenants(&self) -> Result<Vec<Uuid>> {
+        Ok(
+            sqlx::query_scalar("SELECT id FROM public.tenants WHERE sandbox AND status = 'active' ORDER BY id")
+                .fetch_all(&self.pool)
+                .await?,
+        )
+    }
+
+    async fn restore(&self, tenant_id: Uuid) -> Result<RestoreSummary> {
+        let mut tx = self.pool.begin().await?;
+        let summary = restore_snapshot(&mut tx, tenant_id).await?;
+        tx.commit().await?;
+        Ok(summary)
+    }
+
+    async fn record_reset(&self, reset: &SandboxReset) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO public.sandbox_resets \
+             (id, tenant_id, snapshot_id, status, tables_restored, rows_restored, sessions_revoked, error, started_at, finished_at) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)",
+        )
+        .bind(reset.id)
+        .bind(reset.tenant_id)
+        .bind(reset.snapshot_id)
+        .bind(reset.status.as_str())
+        .bind(reset.tables_restored as i32)
+        .bind(reset.rows_restored)
+        .bind(reset.sessions_revoked as i32)
+        .bind(&reset.error)
+        .bind(reset.started_at)
+        .bind(reset.finished_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
+
+/// Sandbox tenant ids the response header middleware checks, reloaded in
+/// the background
+#[derive(Default)]
+pub struct SandboxTenants {
+    ids: RwLock<HashSet<Uuid>>,
+}
+
+impl SandboxTenants {
+    pub fn contains(&self, tenant_id: Uuid) -> bool {
+        self.ids.read().map(|ids| ids.contains(&tenant_id)).unwrap_or(false)
+    }
+
+    pub fn replace(&self, tenant_ids: impl IntoIterator<Item = Uuid>) {
+        if let Ok(mut ids) = self.ids.write() {
+            *ids = tenant_ids.into_iter().collect();
+        }
+    }
+}
+
+/// Resets sandbox tenants to their golden snapshot
+pub struct SandboxResetService {
+    store: Arc<dyn SandboxStore>,
+    sessions: Arc<dyn SessionInvalidator>,
+    config: SandboxConfig,
+    tenants: Arc<SandboxTenants>,
+    /// Without it resets are only recorded and logged
+    announcer: Option<Arc<dyn ResetAnnouncer>>,
+}
+
+impl SandboxResetService {
+    pub fn new(store: Arc<dyn SandboxStore>, sessions: Arc<dyn SessionInvalidator>, config: SandboxConfig) -> Self {
+        Self {
+            store,
+            sessions,
+            config,
+            tenants: Arc::new(SandboxTenants::default()),
+            announcer: None,
+        }
+    }
+
+    /// Announce every reset, failed ones included
+    pub fn with_announcer(mut self, announcer: Arc<dyn ResetAnnouncer>) -> Self {
+        self.announcer = Some(announcer);
+        self
+    }
+
+    /// The sandbox tenants as of the last refresh
+    pub fn tenants(&self) -> Arc<SandboxTenants> {
+        self.tenants.clone()
+    }
+
+    pub async fn refresh_tenants(&self) -> Result<()> {
+        self.tenants.replace(self.store.sandbox_tenants().await?);
+        Ok(())
+    }
+
+    /// Reset every active sandbox tenant; one failing does not stop the others
+    pub async fn reset_all(&self) -> Result<Vec<SandboxReset>> {
+        let tenant_ids = self.store.sandbox_tenants().await?;
+        self.tenants.replace(tenant_ids.iter().copied());
+
+        let mut resets = Vec::with_capacity(tenant_ids.len());
+        for tenant_id in tenant_ids {
+            resets.push(self.reset_tenant(tenant_id).await);
+        }
+        Ok(resets)
+    }
+
+    /// Restore one tenant, then end its sessions
+    pub async fn reset_tenant(&self, tenant_id: Uuid) -> SandboxReset {
+        let started_at = Utc::now();
+        let mut reset = SandboxReset {
+            id: Uuid::new_v4(),
+            tenant_id,
+            snapshot_id: None,
+            status: ResetStatus::Failed,
+            tables_restored: 0,
+            rows_restored: 0,
+            sessions_revoked: 0,
+            error: None,
+            started_at,
+            finished_at: started_at,
+        };
+
+        match self.store.restore(tenant_id).await {
+            Ok(summary) => {
+                reset.status = ResetStatus::Succeeded;
+                reset.snapshot_id = Some(summary.snapshot_id);
+                reset.tables_restored = summary.tables;
+                reset.rows_restored = summary.rows;
+                // The restored users may differ from those logged in; nobody keeps a session across a reset
+                match self.sessions.revoke_tenant_sessions(tenant_id, "sandbox reset").await {
+                    Ok(count) => reset.sessions_revoked = count,
+                    Err(e) => {
+                        error!("Failed to revoke sessions of sandbox tenant {} after its reset: {}", tenant_id, e);
+                        reset.error = Some(format!("Sessions not revoked: {}", e));
+                    }
+                }
+                info!(
+                    "Sandbox tenant {} reset to snapshot {}: {} tables, {} rows, {} sessions revoked",
+                    tenant_id, summary.snapshot_id, summary.tables, summary.rows, reset.sessions_revoked
+                );
+            }
+            Err(e) => {
+                error!("Failed to reset sandbox tenant {}: {}", tenant_id, e);
+                reset.error = Some(e.to_string());
+            }
+        }
+        reset.finished_at = Utc::now();
+
+        if let Err(e) = self.store.record_reset(&reset).await {
+            warn!("Failed to record reset of sandbox tenant {}: {}", tenant_id, e);
+        }
+        if let Some(announcer) = &self.announcer {
+            if let Err(e) = announcer.announce(&reset).await {
+                warn!("Failed to announce reset of sandbox tenant {}: {}", tenant_id, e);
+            }
+        }
+        reset
+    }
+
+    /// Reset every night at `reset_hour_utc` and reload the sandbox tenants
+    /// every `tenant_refresh_secs`, in the background
+    pub fn spawn(self: &Arc<Self>) -> Option<tokio::task::JoinHandle<()>> {
+        let service = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut interval =
+                tokio::time::interval(std::time::Duration::from_secs(service.config.tenant_refresh_secs.max(1)));
+            loop {
+                interval.tick().await;
+                if let Err(e) = service.refresh_tenants().await {
+                    warn!("Failed to load sandbox tenants: {}", e);
+                }
+            }
+        });
+
+        if !self.config.enabled {
+            info!("Nightly sandbox reset disabled");
+            return None;
+        }
+
+        let service = Arc::clone(self);
+        Some(tokio::spawn(async move {
+            loop {
+                let now = Utc::now();
+                let wait = (next_run(now, service.config.reset_hour_utc) - now)
+                    .to_std()
+                    .unwrap_or_default();
+                tokio::time::sleep(wait).await;
+
+                match service.reset_all().await {
+                    Ok(resets) => info!(
+                        "Sandbox reset finished: {} of {} tenants reset",
+                        resets.iter().filter(|reset| reset.status == ResetStatus::Succeeded).count(),
+                        resets.len()
+                    ),
+                    Err(e) => warn!("Sandbox reset run failed: {}", e),
+                }
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Sandboxes in memory; restoring a tenant in `broken` fails
+    #[derive(Default)]
+    struct MemorySandboxes {
+        tenants: Vec<Uuid>,
+        broken: HashSet<Uuid>,
+        resets: Mutex<Vec<SandboxReset>>,
+    }
+
+    #[async_trait]
+    impl SandboxStore for MemorySandboxes {
+        async fn sandbox_tenants(&self) -> Result<Vec<Uuid>> {
+            Ok(self.tenants.clone())
+        }
+
+        async fn restore(&self, tenant_id: Uuid) -> Result<RestoreSummary> {
+            if self.broken.contains(&tenant_id) {
+                return Err(Error::new(ErrorCode::ValidationFailed, "Table 'orders' changed since the snapshot"));
+            }
+            Ok(RestoreSummary {
+                snapshot_id: Uuid::new_v4(),
+                tables: 4,
+                rows: 120,
+            })
+        }
+
+        async fn record_reset(&self, reset: &SandboxReset) -> Result<()> {
+            self.resets.lock().unwrap().push(reset.clone());
+            Ok(())
+        }
+    }
+
+    #[derive(Default)]
+    struct RecordingSessions {
+        revoked: Mutex<Vec<Uuid>>,
+    }
+
+    #[async_trait]
+    impl SessionInvalidator for RecordingSessions {
+        async fn revoke_tenant_sessions(&self, tenant_id: Uuid, _reason: &str) -> Result<u32> {
+            self.revoked.lock().unwrap().push(tenant_id);
+            Ok(3)
+        }
+    }
+
+    #[derive(Default)]
+    struct RecordingAnnouncer {
+        announced: Mutex<Vec<(Uuid, ResetStatus)>>,
+    }
+
+    #[async_trait]
+    impl ResetAnnouncer for RecordingAnnouncer {
+        async fn announce(&self, reset: &SandboxReset) -> Result<()> {
+            self.announced.lock().unwrap().push((reset.tenant_id, reset.status));
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sessions_are_revoked_only_after_a_successful_reset() {
+        let (healthy, broken) = (Uuid::new_v4(), Uuid::new_v4());
+        let store = Arc::new(MemorySandboxes {
+            tenants: vec![healthy, broken],
+            broken: HashSet::from([broken]),
+            ..Default::default()
+        });
+        let sessions = Arc::new(RecordingSessions::default());
+        let announcer = Arc::new(RecordingAnnouncer::default());
+        let service = SandboxResetService::new(store.clone(), sessions.clone(), SandboxConfig::default())
+            .with_announcer(announcer.clone());
+
+        let resets = service.reset_all().await.unwrap();
+
+        assert_eq!(resets[0].status, ResetStatus::Succeeded);
+        assert_eq!(resets[0].sessions_revoked, 3);
+        assert_eq!(resets[0].rows_restored, 120);
+        assert_eq!(resets[1].status, ResetStatus::Failed);
+        assert!(resets[1].error.as_deref().unwrap().contains("orders"));
+        assert_eq!(*sessions.revoked.lock().unwrap(), vec![healthy]);
+
+        // Both are recorded and announced, the failure included
+        assert_eq!(store.resets.lock().unwrap().len(), 2);
+        assert_eq!(
+            *announcer.announced.lock().unwrap(),
+            vec![(healthy, ResetStatus::Succeeded), (broken, ResetStatus::Failed)]
+        );
+        assert!(service.tenants().contains(healthy));
+        assert!(!service.tenants().contains(Uuid::new_v4()));
+    }
+}
@@ -1,27 +1,635 @@
 use erp_auth::AuthService;
-use erp_core::{Config, DatabasePool, TenantContext};
+use erp_core::{outbound::OutboundClientFactory, ActorContext, Config, DatabasePool, HttpMetrics, MetricsRegistry, RequestContext, TenantContext};
+use erp_core::latency::LatencyBudget;
+use erp_core::numbering::BlockAllocator;
+use erp_core::plan_limits::PlanLimitGuard;
+use erp_core::portal::PortalScope;
+use erp_master_data::customer::{
+    CommunicationService, CustomerFieldCipher, DefaultCommunicationService, DefaultTerritoryService,
+    PostgresCommunicationRepository, PostgresTerritoryRepository, ScopedCustomerRepository, TerritoryService,
+};
+use erp_master_data::currency::{
+    DefaultExchangeRateService, EcbRateProvider, ExchangeRateProvider, ExchangeRateRepository, ExchangeRateService,
+    ManualRateProvider, PostgresExchangeRateRepository,
+};
 use erp_master_data::customer::repository::{CustomerRepository, PostgresCustomerRepository};
 use erp_master_data::customer::service::{CustomerService, DefaultCustomerService};
+use erp_master_data::inventory::{
+    CapacitySettings, DefaultLocationCapacityService, DefaultPurchaseReceiptService, LocationCapacityService,
+    PostgresLocationCapacityRepository, PostgresPurchaseReceiptRepository, PurchaseReceiptService, ReceivingSettings,
+    ConsignmentService, DefaultConsignmentService, PostgresConsignmentRepository,
+    AvailableToPromiseService, DefaultAvailableToPromiseService, PostgresAvailableToPromiseRepository,
+    DefaultInventorySnapshotService, InventorySnapshotService, PostgresInventorySnapshotRepository,
+    DefaultInventoryCostingService, InventoryCostingService, PostgresInventoryCostingRepository,
+    DefaultInventoryValuationService, InventoryValuationService, PostgresInventoryValuationRepository,
+    DefaultForecastScenarioService, DefaultPickingService, DefaultReturnsService, DefaultSerialTrackingService, DefaultStocktakeService,
+    ForecastScenarioService, InventoryOptimizationEngine, OptimizationReportRepository,
+    PickingService, PostgresForecastScenarioRepository, PostgresInventoryOptimizationEngine, PostgresInventoryRepository,
+    PostgresOptimizationReportRepository, PostgresPickWaveRepository, PostgresReturnOrderRepository, PostgresSerialUnitRepository,
+    PostgresStockTransferRepository, PostgresStocktakeRepository, ReturnsService, SerialTrackingService,
+    StockTransferService, DefaultStockTransferService, StocktakeService,
+};
+use erp_master_data::product::{
+    BarcodeService, BulkPriceUpdateService, DefaultBarcodeService, DefaultBulkPriceUpdateService,
+    DefaultLifecycleAutomationService, DefaultPriceListService, DefaultProductSuggestionService,
+    DefaultQualityInspectionService, InspectionRules, LifecycleAutomationMode, LifecycleAutomationService, LifecycleRules,
+    PostgresProductRepository, PriceGuardConfig, PriceListService, ProductIntegrityRepository, ProductSuggestionService,
+    QualityInspectionService, RepositoryProductUpdates,
+};
+use erp_master_data::supplier::{
+    DefaultSupplierCatalogService, PostgresSupplierCatalogRepository, SupplierCatalogService,
+};
+use erp_core::audit::AuditBackend;
 use redis::aio::ConnectionManager;
 use std::sync::Arc;
 
+use crate::{
+    activity::ActivityService, api_middleware::{api_version::ApiVersionPolicy, rate_limit::RateLimiter}, api_usage::ApiUsageService, backpressure::Backpressure,
+    business_calendar::CalendarStore,
+    existence_checks::ExistenceChecks,
+    global_search::{CustomerSearchSource, GlobalSearch, PostgresSearchSource, SearchSource, SearchType},
+    idempotency::Idempotency,
+    inventory_alerts::{InventoryAlertStreams, RedisAlertPublisher},
+    credit_standing::CreditStandingService,
+    follow_up_reminders::FollowUpReminderService, job_health::JobHealthMonitor, letterhead::LetterheadStore,
+    notification_digest::NotificationService, permission_usage::PermissionUsageService, plan_limits::PlanLimitService,
+    reservation_reconciliation::ReservationReconciliationService, retention::RetentionService,
+    sandbox::SandboxTenants,
+    sync::SyncService, tenant_health::TenantHealthMonitor, transfer_approvals::TransferApprovalNotifier,
+    webhooks::WebhookService,
+};
+
 #[derive(Clone)]
 pub struct AppState {
     pub config: Config,
     pub db: DatabasePool,
     pub redis: ConnectionManager,
     pub auth_service: Arc<AuthService>,
+    pub metrics: MetricsRegistry,
+    /// Request durations and error counts per route, served with the registry at `metrics.path`
+    pub http_metrics: HttpMetrics,
+    pub tenant_health: Arc<TenantHealthMonitor>,
+    /// Consumer heartbeats and backlog of the job queues, reported by `/ready`
+    pub job_health: Arc<JobHealthMonitor>,
+    pub sync: Arc<SyncService>,
+    pub api_versions: Arc<ApiVersionPolicy>,
+    pub follow_up_reminders: Arc<FollowUpReminderService>,
+    pub retention: Arc<RetentionService>,
+    /// Which permissions are exercised, for least-privilege reviews
+    pub permission_usage: Arc<PermissionUsageService>,
+    /// Calls per access token for the usage reports, and the per-token rate limit
+    pub api_usage: Arc<ApiUsageService>,
+    /// Whether emails, SKUs and customer numbers are taken, for form validation
+    pub existence_checks: Arc<ExistenceChecks>,
+    /// Builds HTTP clients for calls to partner systems
+    pub outbound: Arc<OutboundClientFactory>,
+    /// Per-tenant activity feed across audit, customer and inventory events
+    pub activity: Arc<ActivityService>,
+    /// Audit events as written by the auth service, for the audit trail queries
+    pub audit: Arc<dyn AuditBackend>,
+    /// Tenant webhook subscriptions and their deliveries across event sources
+    pub webhooks: Arc<WebhookService>,
+    /// Delivers user notifications, immediately or in the daily digest
+    pub notifications: Arc<NotificationService>,
+    /// Notifies approvers of stock transfers and escalates overdue approvals
+    pub transfer_approvals: Arc<TransferApprovalNotifier>,
+    /// Reconciles reserved inventory quantities with their reservations
+    pub reservation_reconciliation: Arc<ReservationReconciliationService>,
+    /// Tenant usage measured against plan limits, with the alerts on their thresholds
+    pub plan_limits: Arc<PlanLimitService>,
+    /// Refuses customers and products beyond an enforced plan limit
+    pub plan_limit_guard: Arc<dyn PlanLimitGuard>,
+    /// Encrypts customer tax numbers and bank accounts; `None` when disabled
+    pub customer_cipher: Option<Arc<CustomerFieldCipher>>,
+    /// Tenant working days and holidays that scheduled jobs follow
+    pub calendars: Arc<dyn CalendarStore>,
+    /// Legal entity details printed at the top of exports
+    pub letterheads: Arc<dyn LetterheadStore>,
+    /// Open items pushed by the accounting system and the credit standing recomputed from them
+    pub credit_standing: Arc<CreditStandingService>,
+    /// Block-allocated document numbers of sequences that may have gaps
+    pub numbering: Arc<BlockAllocator>,
+    /// Sandbox tenant ids whose responses carry `X-Sandbox: true`
+    pub sandbox_tenants: Arc<SandboxTenants>,
+    /// Concurrency limits of expensive endpoints and the results of requests answered asynchronously
+    pub backpressure: Arc<Backpressure>,
+    /// Claimed `Idempotency-Key`s of create requests and the responses replayed for them
+    pub idempotency: Arc<Idempotency>,
+    /// Sliding-window limits per caller and route bucket, if `rate_limit.enabled`
+    pub rate_limiter: Option<Arc<RateLimiter>>,
+    /// Type-ahead search across customers, products, suppliers and locations, with its short-lived cache
+    pub global_search: Arc<GlobalSearch>,
+    /// Open inventory alert WebSocket streams per tenant
+    pub inventory_alerts: Arc<InventoryAlertStreams>,
+    /// Soft latency budgets of master data service methods, observed in the metrics
+    pub latency_budget: LatencyBudget,
 }
 
 impl AppState {
     /// Create a CustomerRepository for a specific tenant context
     pub fn customer_repository(&self, tenant_context: TenantContext) -> Box<dyn CustomerRepository> {
-        Box::new(PostgresCustomerRepository::new(self.db.main_pool.clone(), tenant_context))
+        let repository = PostgresCustomerRepository::new(self.db.main_pool.clone(), tenant_context)
+            .with_number_allocator(self.numbering.clone());
+        match &self.customer_cipher {
+            Some(cipher) => Box::new(repository.with_field_cipher(cipher.clone())),
+            None => Box::new(repository),
+        }
     }
 
     /// Create a CustomerService for a specific tenant context with business logic
     pub fn customer_service(&self, tenant_context: TenantContext) -> Box<dyn CustomerService> {
         let repository = self.customer_repository(tenant_context.clone());
-        Box::new(DefaultCustomerService::new(repository, tenant_context))
+        Box::new(
+            DefaultCustomerService::new(repository, tenant_context)
+                .with_plan_limits(self.plan_limit_guard.clone())
+                .with_latency_budget(self.latency_budget.clone()),
+        )
+    }
+
+    /// A CustomerRepository limited to the customers a portal account may see;
+    /// unlimited without a portal scope
+    pub fn scoped_customer_repository(
+        &self,
+        tenant_context: TenantContext,
+        scope: Option<PortalScope>,
+    ) -> Box<dyn CustomerRepository> {
+        let repository = self.customer_repository(tenant_context);
+        match scope {
+            Some(scope) => Box::new(ScopedCustomerRepository::new(repository, scope)),
+            None => repository,
+        }
+    }
+
+    /// A CustomerService over [`Self::scoped_customer_repository`]
+    pub fn scoped_customer_service(
+        &self,
+        tenant_context: TenantContext,
+        scope: Option<PortalScope>,
+    ) -> Box<dyn CustomerService> {
+        let repository = self.scoped_customer_repository(tenant_context.clone(), scope);
+        Box::new(
+            DefaultCustomerService::new(repository, tenant_context)
+                .with_plan_limits(self.plan_limit_guard.clone())
+                .with_latency_budget(self.latency_budget.clone()),
+        )
+    }
+
+    /// The master data context of a request, acting as its actor with its permissions
+    fn acting_context(&self, tenant_context: &TenantContext, request_context: &RequestContext) -> erp_master_data::TenantContext {
+        let mut context = self.actor_context(tenant_context, request_context.actor());
+        context.permissions = request_context.permissions.iter().map(|p| p.to_string()).collect();
+        context
+    }
+
+    /// The master data context acting as `actor`; without one its user is
+    /// nil, and repositories refuse writes made by the nil UUID
+    fn actor_context(&self, tenant_context: &TenantContext, actor: ActorContext) -> erp_master_data::TenantContext {
+        erp_master_data::TenantContext::new(
+            tenant_context.tenant_id.0,
+            tenant_context.schema_name.clone(),
+            actor.id().unwrap_or_default(),
+        )
+    }
+
+    /// Create a SerialTrackingService for a specific tenant context
+    /// The global search sources of a tenant; customers through the scoped customer service
+    pub fn search_sources(
+        &self,
+        tenant_context: &TenantContext,
+        scope: Option<PortalScope>,
+    ) -> Vec<Arc<dyn SearchSource>> {
+        let customers = CustomerSearchSource::new(self.scoped_customer_service(tenant_context.clone(), scope));
+        let mut sources: Vec<Arc<dyn SearchSource>> = vec![Arc::new(customers)];
+        for search_type in [SearchType::Products, SearchType::Suppliers, SearchType::Locations] {
+            sources.push(Arc::new(PostgresSearchSource::new(
+                self.db.main_pool.clone(),
+                tenant_context.tenant_id.0,
+                search_type,
+            )));
+        }
+        sources
+    }
+
+    pub fn serial_tracking_service(&self, tenant_context: &TenantContext, actor: ActorContext) -> Box<dyn SerialTrackingService> {
+        let context = self.actor_context(tenant_context, actor);
+        Box::new(DefaultSerialTrackingService::new(
+            Arc::new(PostgresSerialUnitRepository::new(self.db.main_pool.clone())),
+            Arc::new(PostgresProductRepository::new(self.db.clone())),
+            context,
+        ))
+    }
+
+    /// Create the inventory optimization engine; rebalancing respects location capacities
+    pub fn inventory_optimization_engine(&self) -> Box<dyn InventoryOptimizationEngine> {
+        Box::new(
+            PostgresInventoryOptimizationEngine::new(self.db.main_pool.clone())
+                .with_capacity_settings(self.capacity_settings()),
+        )
+    }
+
+    fn capacity_settings(&self) -> CapacitySettings {
+        let config = &self.config.location_capacity;
+        CapacitySettings {
+            warn_percent: config.warn_percent,
+            pallet_volume_m3: config.pallet_volume_m3,
+            default_unit_volume_m3: config.default_unit_volume_m3,
+        }
+    }
+
+    /// Create a repository for stored optimization reports
+    pub fn optimization_report_repository(&self) -> Box<dyn OptimizationReportRepository> {
+        Box::new(PostgresOptimizationReportRepository::new(self.db.main_pool.clone()))
+    }
+
+    /// Create an inventory repository whose created alerts are published to the tenant's alert streams
+    pub fn inventory_repository(&self, tenant_context: &TenantContext) -> Arc<PostgresInventoryRepository> {
+        Arc::new(
+            PostgresInventoryRepository::new(self.db.main_pool.clone())
+                .with_quarantine_location_types(self.config.returns.quarantine_location_types.clone())
+                .with_alert_publisher(Arc::new(RedisAlertPublisher::new(self.redis.clone(), tenant_context.tenant_id.0))),
+        )
+    }
+
+    /// Create a ForecastScenarioService; opening stock excludes quarantine locations
+    pub fn forecast_scenario_service(&self, tenant_context: &TenantContext, actor: ActorContext) -> Box<dyn ForecastScenarioService> {
+        let context = self.actor_context(tenant_context, actor);
+        Box::new(DefaultForecastScenarioService::new(
+            Arc::new(PostgresForecastScenarioRepository::new(self.db.main_pool.clone())),
+            self.inventory_repository(tenant_context),
+            Arc::new(PostgresInventoryOptimizationEngine::new(self.db.main_pool.clone())),
+            context,
+        ))
+    }
+
+    /// Create a StocktakeService acting as the authenticated user
+    pub fn stocktake_service(
+        &self,
+        tenant_context: &TenantContext,
+        request_context: &RequestContext,
+    ) -> Box<dyn StocktakeService> {
+        let context = self.acting_context(tenant_context, request_context);
+
+        Box::new(DefaultStocktakeService::new(
+            Arc::new(PostgresStocktakeRepository::new(self.db.main_pool.clone())),
+            self.inventory_repository(tenant_context),
+            context,
+        ))
+    }
+
+    /// Create a ReturnsService acting as the authenticated user
+    pub fn returns_service(
+        &self,
+        tenant_context: &TenantContext,
+        request_context: &RequestContext,
+    ) -> Box<dyn ReturnsService> {
+        let context = self.acting_context(tenant_context, request_context);
+
+        Box::new(DefaultReturnsService::new(
+            Arc::new(PostgresReturnOrderRepository::new(self.db.main_pool.clone())),
+            context,
+            self.config.returns.quarantine_location_types.clone(),
+        ))
+    }
+
+    /// Create a StockTransferService acting as the authenticated user
+    pub fn stock_transfer_service(
+        &self,
+        tenant_context: &TenantContext,
+        request_context: &RequestContext,
+    ) -> Box<dyn StockTransferService> {
+        let context = self.acting_context(tenant_context, request_context);
+
+        let capacity = self.location_capacity_service(tenant_context, request_context);
+        Box::new(
+            DefaultStockTransferService::new(
+                Arc::new(PostgresStockTransferRepository::new(self.db.main_pool.clone())),
+                context,
+            )
+            .with_capacity_checks(Arc::from(capacity)),
+        )
+    }
+
+    /// Create a LocationCapacityService acting as the authenticated user
+    pub fn location_capacity_service(
+        &self,
+        tenant_context: &TenantContext,
+        request_context: &RequestContext,
+    ) -> Box<dyn LocationCapacityService> {
+        let context = self.acting_context(tenant_context, request_context);
+
+        Box::new(DefaultLocationCapacityService::new(
+            Arc::new(PostgresLocationCapacityRepository::new(self.db.main_pool.clone())),
+            context,
+            self.capacity_settings(),
+        ))
+    }
+
+    /// Create a PurchaseReceiptService acting as the authenticated user
+    pub fn purchase_receipt_service(
+        &self,
+        tenant_context: &TenantContext,
+        request_context: &RequestContext,
+    ) -> Box<dyn PurchaseReceiptService> {
+        let context = self.acting_context(tenant_context, request_context);
+        let capacity = self.location_capacity_service(tenant_context, request_context);
+
+        let inspections = self.quality_inspection_service(tenant_context, request_context);
+
+        Box::new(
+            DefaultPurchaseReceiptService::new(
+                Arc::new(PostgresPurchaseReceiptRepository::new(self.db.main_pool.clone())),
+                Arc::from(capacity),
+                context,
+                ReceivingSettings {
+                    over_receipt_tolerance_percent: self.config.purchase_receiving.over_receipt_tolerance_percent,
+                },
+            )
+            .with_inspections(Arc::from(inspections)),
+        )
+    }
+
+    pub fn consignment_service(
+        &self,
+        tenant_context: &TenantContext,
+        request_context: &RequestContext,
+    ) -> Box<dyn ConsignmentService> {
+        let context = self.acting_context(tenant_context, request_context);
+        let capacity = self.location_capacity_service(tenant_context, request_context);
+
+        Box::new(DefaultConsignmentService::new(
+            Arc::new(PostgresConsignmentRepository::new(self.db.main_pool.clone())),
+            Arc::from(capacity),
+            context,
+        ))
+    }
+
+    /// Create an AvailableToPromiseService; stock at quarantine locations is never promised
+    pub fn available_to_promise_service(
+        &self,
+        tenant_context: &TenantContext,
+        request_context: &RequestContext,
+    ) -> Box<dyn AvailableToPromiseService> {
+        let context = self.acting_context(tenant_context, request_context);
+
+        Box::new(DefaultAvailableToPromiseService::new(
+            Arc::new(
+                PostgresAvailableToPromiseRepository::new(self.db.main_pool.clone())
+                    .with_quarantine_location_types(self.config.returns.quarantine_location_types.clone()),
+            ),
+            context,
+        ))
+    }
+
+    /// Create an InventorySnapshotService acting as the authenticated user
+    pub fn inventory_snapshot_service(
+        &self,
+        tenant_context: &TenantContext,
+        request_context: &RequestContext,
+    ) -> Box<dyn InventorySnapshotService> {
+        let context = self.acting_context(tenant_context, request_context);
+
+        Box::new(DefaultInventorySnapshotService::new(
+            Arc::new(PostgresInventorySnapshotRepository::new(self.db.main_pool.clone())),
+            context,
+        ))
+    }
+
+    /// Create an InventoryCostingService acting as the authenticated user
+    pub fn inventory_costing_service(
+        &self,
+        tenant_context: &TenantContext,
+        request_context: &RequestContext,
+    ) -> Box<dyn InventoryCostingService> {
+        let context = self.acting_context(tenant_context, request_context);
+
+        Box::new(DefaultInventoryCostingService::new(
+            Arc::new(PostgresInventoryCostingRepository::new(self.db.main_pool.clone())),
+            context,
+        ))
+    }
+
+    /// Rates of the tenant itself, falling back to the ECB reference rates when they are fetched
+    fn exchange_rate_provider(&self, repository: Arc<dyn ExchangeRateRepository>) -> Arc<dyn ExchangeRateProvider> {
+        let manual = ManualRateProvider::new(repository.clone());
+        if self.config.exchange_rates.ecb_enabled {
+            Arc::new(manual.with_fallback(Arc::new(EcbRateProvider::new(repository))))
+        } else {
+            Arc::new(manual)
+        }
+    }
+
+    /// Create an ExchangeRateService acting as the authenticated user
+    pub fn exchange_rate_service(
+        &self,
+        tenant_context: &TenantContext,
+        request_context: &RequestContext,
+    ) -> Box<dyn ExchangeRateService> {
+        let context = self.acting_context(tenant_context, request_context);
+
+        let repository: Arc<dyn ExchangeRateRepository> =
+            Arc::new(PostgresExchangeRateRepository::new(self.db.main_pool.clone()));
+        let provider = self.exchange_rate_provider(repository.clone());
+        Box::new(DefaultExchangeRateService::new(repository, provider, context))
+    }
+
+    /// Create an InventoryValuationService reporting in the tenant's base currency
+    pub fn inventory_valuation_service(
+        &self,
+        tenant_context: &TenantContext,
+        request_context: &RequestContext,
+    ) -> Box<dyn InventoryValuationService> {
+        let context = self.acting_context(tenant_context, request_context);
+
+        let rates: Arc<dyn ExchangeRateRepository> =
+            Arc::new(PostgresExchangeRateRepository::new(self.db.main_pool.clone()));
+        let provider = self.exchange_rate_provider(rates.clone());
+        Box::new(DefaultInventoryValuationService::new(
+            Arc::new(PostgresInventoryValuationRepository::new(self.db.main_pool.clone())),
+            rates,
+            provider,
+            context,
+        ))
+    }
+
+    /// Create a PickingService acting as the authenticated user
+    pub fn picking_service(
+        &self,
+        tenant_context: &TenantContext,
+        request_context: &RequestContext,
+    ) -> Box<dyn PickingService> {
+        let context = self.acting_context(tenant_context, request_context);
+
+        Box::new(DefaultPickingService::new(
+            Arc::new(PostgresPickWaveRepository::new(self.db.main_pool.clone())),
+            context,
+        ))
+    }
+
+    /// Create a CommunicationService scoped to the authenticated user
+    pub fn communication_service(
+        &self,
+        tenant_context: &TenantContext,
+        request_context: &RequestContext,
+    ) -> Box<dyn CommunicationService> {
+        let context = self.acting_context(tenant_context, request_context);
+
+        Box::new(DefaultCommunicationService::new(
+            Arc::new(PostgresCommunicationRepository::new(self.db.main_pool.clone())),
+            context,
+        ))
+    }
+
+    /// Create a TerritoryService acting as the authenticated user
+    pub fn territory_service(
+        &self,
+        tenant_context: &TenantContext,
+        request_context: &RequestContext,
+    ) -> Box<dyn TerritoryService> {
+        let context = self.acting_context(tenant_context, request_context);
+
+        Box::new(DefaultTerritoryService::new(
+            Arc::new(PostgresTerritoryRepository::new(self.db.main_pool.clone())),
+            context,
+        ))
+    }
+
+    /// Create a BulkPriceUpdateService acting as the authenticated user
+    pub fn bulk_price_update_service(
+        &self,
+        tenant_context: &TenantContext,
+        request_context: &RequestContext,
+    ) -> Box<dyn BulkPriceUpdateService> {
+        let context = self.acting_context(tenant_context, request_context);
+        let guards = PriceGuardConfig {
+            max_change_percent: self.config.bulk_pricing.max_change_percent,
+        };
+
+        Box::new(DefaultBulkPriceUpdateService::new(
+            Arc::new(PostgresProductRepository::new(self.db.clone())),
+            context,
+            guards,
+        ))
+    }
+
+    /// Create a BarcodeService acting as the authenticated user
+    pub fn barcode_service(
+        &self,
+        tenant_context: &TenantContext,
+        request_context: &RequestContext,
+    ) -> Box<dyn BarcodeService> {
+        let context = self.acting_context(tenant_context, request_context);
+
+        Box::new(DefaultBarcodeService::new(
+            Arc::new(PostgresProductRepository::new(self.db.clone())),
+            context,
+        ))
     }
-}
\ No newline at end of file
+
+    /// Create a PriceListService acting as the authenticated user
+    pub fn price_list_service(
+        &self,
+        tenant_context: &TenantContext,
+        request_context: &RequestContext,
+    ) -> Box<dyn PriceListService> {
+        let context = self.acting_context(tenant_context, request_context);
+
+        Box::new(DefaultPriceListService::new(
+            Arc::new(PostgresProductRepository::new(self.db.clone())),
+            context,
+        ))
+    }
+
+    /// Create a SupplierCatalogService acting as the authenticated user
+    pub fn supplier_catalog_service(
+        &self,
+        tenant_context: &TenantContext,
+        request_context: &RequestContext,
+    ) -> Box<dyn SupplierCatalogService> {
+        let context = self.acting_context(tenant_context, request_context);
+
+        Box::new(DefaultSupplierCatalogService::new(
+            Arc::new(PostgresSupplierCatalogRepository::new(self.db.clone())),
+            context,
+        ))
+    }
+
+    /// Create a ProductSuggestionService acting as the authenticated user
+    pub fn product_suggestion_service(
+        &self,
+        tenant_context: &TenantContext,
+        request_context: &RequestContext,
+    ) -> Box<dyn ProductSuggestionService> {
+        let context = self.acting_context(tenant_context, request_context);
+        let repository = Arc::new(PostgresProductRepository::new(self.db.clone()));
+
+        Box::new(
+            DefaultProductSuggestionService::new(
+                repository.clone(),
+                Arc::new(RepositoryProductUpdates::new(repository.clone(), context.clone())),
+                context,
+            )
+            .with_lifecycle(repository),
+        )
+    }
+
+    /// Create a repository for the product integrity check and repair
+    pub fn product_integrity_repository(&self) -> Box<dyn ProductIntegrityRepository> {
+        Box::new(PostgresProductRepository::new(self.db.clone()))
+    }
+
+    /// Create a LifecycleAutomationService acting as the authenticated user
+    pub fn lifecycle_automation_service(
+        &self,
+        tenant_context: &TenantContext,
+        request_context: &RequestContext,
+    ) -> Box<dyn LifecycleAutomationService> {
+        let context = self.acting_context(tenant_context, request_context);
+
+        Box::new(DefaultLifecycleAutomationService::new(
+            Arc::new(PostgresProductRepository::new(self.db.clone())),
+            context,
+            lifecycle_rules(&self.config.product_lifecycle),
+        ))
+    }
+
+    /// Create a QualityInspectionService acting as the authenticated user
+    pub fn quality_inspection_service(
+        &self,
+        tenant_context: &TenantContext,
+        request_context: &RequestContext,
+    ) -> Box<dyn QualityInspectionService> {
+        let context = self.acting_context(tenant_context, request_context);
+
+        Box::new(DefaultQualityInspectionService::new(
+            Arc::new(PostgresProductRepository::new(self.db.clone())),
+            context,
+            inspection_rules(&self.config.quality_inspections),
+        ))
+    }
+}
+
+/// The lifecycle rules of the `[product_lifecycle]` configuration
+pub fn lifecycle_rules(config: &erp_core::ProductLifecycleConfig) -> LifecycleRules {
+    LifecycleRules {
+        default_mode: LifecycleAutomationMode::parse(&config.default_mode).unwrap_or(LifecycleAutomationMode::Suggest),
+        window_days: config.window_days.into(),
+        introduction_max_days: config.introduction_max_days.into(),
+        maturity_after_days: config.maturity_after_days.into(),
+        growth_trend_percent: config.growth_trend_percent,
+        decline_trend_percent: config.decline_trend_percent,
+        contribution_decline_percent: config.contribution_decline_percent,
+        min_units: config.min_units,
+        manual_freeze_days: config.manual_freeze_days.into(),
+    }
+}
+
+/// The scheduling rules of the `[quality_inspections]` configuration
+pub fn inspection_rules(config: &erp_core::QualityInspectionConfig) -> InspectionRules {
+    InspectionRules {
+        incoming_due_days: config.incoming_due_days.into(),
+        incoming_priority: config.incoming_priority,
+        reinspect_days_before_expiry: config.reinspect_days_before_expiry.into(),
+        schedule_ahead_days: config.schedule_ahead_days.into(),
+        reinspection_priority: config.reinspection_priority,
+    }
+}
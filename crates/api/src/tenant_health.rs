@@ -0,0 +1,532 @@
+//! # Tenant Health Monitoring
+//!
+//! Per-tenant schema health checks backing the admin endpoints under
+//! `/api/v1/admin/tenants`. Global readiness (`/ready`) only proves the shared
+//! database is reachable; this module probes every tenant schema individually so a
+//! single broken tenant is visible before its users report it.
+//!
+//! ## Checks per tenant
+//!
+//! - **migrations**: schema version recorded for the tenant vs. the version of the tenant template
+//! - **sentinel**: row count of a table every tenant schema must contain
+//! - **activity**: users that logged in during the last 24 hours
+//! - **provisioning**: provisioning steps that never reached `complete`
+//!
+//! Tenants are probed concurrently with bounded parallelism and an overall timeout.
+//! Results are cached for `tenant_health.cache_ttl_seconds`.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use erp_core::TenantHealthConfig;
+use prometheus::{IntCounterVec, Opts};
+use serde::Serialize;
+use sqlx::{PgPool, Row};
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::{sync::{RwLock, Semaphore}, task::JoinSet};
+use tracing::warn;
+use uuid::Uuid;
+
+/// Overall state of a tenant or a single check
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthStatus {
+    Ok,
+    Degraded,
+    Failed,
+    /// The check did not finish within the overall timeout
+    Timeout,
+}
+
+impl HealthStatus {
+    pub fn is_ok(self) -> bool {
+        self == HealthStatus::Ok
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            HealthStatus::Ok => "ok",
+            HealthStatus::Degraded => "degraded",
+            HealthStatus::Failed => "failed",
+            HealthStatus::Timeout => "timeout",
+        }
+    }
+}
+
+/// Tenant as listed in `public.tenants`
+#[derive(Debug, Clone, Serialize)]
+pub struct TenantRecord {
+    pub id: Uuid,
+    pub name: String,
+    pub schema_name: String,
+}
+
+/// Result of a single check with its latency
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckResult {
+    pub name: String,
+    pub status: HealthStatus,
+    pub latency_ms: u64,
+    pub detail: Option<String>,
+}
+
+impl CheckResult {
+    pub fn new(name: &str, status: HealthStatus, started: Instant, detail: Option<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            status,
+            latency_ms: started.elapsed().as_millis() as u64,
+            detail,
+        }
+    }
+}
+
+/// Health of a single tenant schema
+#[derive(Debug, Clone, Serialize)]
+pub struct TenantHealthReport {
+    pub tenant_id: Uuid,
+    pub tenant_name: String,
+    pub schema_name: String,
+    pub status: HealthStatus,
+    pub schema_version: Option<i64>,
+    pub expected_schema_version: i64,
+    pub sentinel_rows: Option<i64>,
+    pub last_write_at: Option<DateTime<Utc>>,
+    pub active_users_24h: Option<i64>,
+    pub stuck_provisioning_steps: Vec<String>,
+    pub checks: Vec<CheckResult>,
+    pub checked_at: DateTime<Utc>,
+}
+
+impl TenantHealthReport {
+    /// Report without any checks yet
+    pub fn new(tenant: &TenantRecord, expected_schema_version: i64) -> Self {
+        Self {
+            tenant_id: tenant.id,
+            tenant_name: tenant.name.clone(),
+            schema_name: tenant.schema_name.clone(),
+            status: HealthStatus::Ok,
+            schema_version: None,
+            expected_schema_version,
+            sentinel_rows: None,
+            last_write_at: None,
+            active_users_24h: None,
+            stuck_provisioning_steps: Vec::new(),
+            checks: Vec::new(),
+            checked_at: Utc::now(),
+        }
+    }
+
+    /// Report for a tenant whose probe could not run at all
+    fn unavailable(tenant: &TenantRecord, expected_schema_version: i64, status: HealthStatus, detail: &str) -> Self {
+        let mut report = Self::new(tenant, expected_schema_version);
+        report.status = status;
+        report.checks.push(CheckResult {
+            name: "probe".to_string(),
+            status,
+            latency_ms: 0,
+            detail: Some(detail.to_string()),
+        });
+        report
+    }
+
+    /// Worst status of all checks
+    pub fn finalize(mut self) -> Self {
+        self.status = self
+            .checks
+            .iter()
+            .map(|c| c.status)
+            .max_by_key(|s| match s {
+                HealthStatus::Ok => 0,
+                HealthStatus::Degraded => 1,
+                HealthStatus::Timeout => 2,
+                HealthStatus::Failed => 3,
+            })
+            .unwrap_or(HealthStatus::Ok);
+        self
+    }
+}
+
+/// Aggregated health across all tenants
+#[derive(Debug, Clone, Serialize)]
+pub struct TenantHealthSummary {
+    pub total: usize,
+    pub healthy: usize,
+    pub unhealthy: usize,
+    pub cached: bool,
+    pub generated_at: DateTime<Utc>,
+    pub tenants: Vec<TenantHealthReport>,
+}
+
+/// Source of tenant health data; abstracted so the runner can be tested without a database
+#[async_trait]
+pub trait TenantProbe: Send + Sync {
+    async fn list_tenants(&self) -> Result<Vec<TenantRecord>, sqlx::Error>;
+
+    async fn probe(&self, tenant: &TenantRecord, expected_schema_version: i64) -> TenantHealthReport;
+}
+
+/// Probe implementation backed by PostgreSQL
+pub struct PostgresTenantProbe {
+    pool: PgPool,
+    sentinel_table: String,
+}
+
+impl PostgresTenantProbe {
+    pub fn new(pool: PgPool, sentinel_table: String) -> Self {
+        Self { pool, sentinel_table }
+    }
+
+    fn is_safe_identifier(name: &str) -> bool {
+        !name.is_empty()
+            && name.len() <= 63
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    }
+}
+
+#[async_trait]
+impl TenantProbe for PostgresTenantProbe {
+    async fn list_tenants(&self) -> Result<Vec<TenantRecord>, sqlx::Error> {
+        let rows = sqlx::query(
+            "SELECT id, name, schema_name FROM public.tenants WHERE status = 'active' ORDER BY name",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| TenantRecord {
+                id: row.get("id"),
+                name: row.get("name"),
+                schema_name: row.get("schema_name"),
+            })
+            .collect())
+    }
+
+    async fn probe(&self, tenant: &TenantRecord, expected_schema_version: i64) -> TenantHealthReport {
+        if !Self::is_safe_identifier(&tenant.schema_name) || !Self::is_safe_identifier(&self.sentinel_table) {
+            return TenantHealthReport::unavailable(
+                tenant,
+                expected_schema_version,
+                HealthStatus::Failed,
+                "Invalid schema or sentinel table name",
+            );
+        }
+
+        let mut report = TenantHealthReport::new(tenant, expected_schema_version);
+
+        // Migration version
+        let started = Instant::now();
+        match sqlx::query_scalar::<_, Option<i64>>(
+            "SELECT MAX(version) FROM public.tenant_schema_migrations WHERE tenant_id = $1 AND success",
+        )
+        .bind(tenant.id)
+        .fetch_one(&self.pool)
+        .await
+        {
+            Ok(version) => {
+                report.schema_version = version;
+                let (status, detail) = match version {
+                    Some(v) if v >= expected_schema_version => (HealthStatus::Ok, None),
+                    Some(v) => (HealthStatus::Degraded, Some(format!("Schema at version {}, expected {}", v, expected_schema_version))),
+                    None => (HealthStatus::Degraded, Some("No successful migrations recorded".to_string())),
+                };
+                report.checks.push(CheckResult::new("migrations", status, started, detail));
+            }
+            Err(e) => report.checks.push(CheckResult::new("migrations", HealthStatus::Failed, started, Some(e.to_string()))),
+        }
+
+        // Sentinel table probe and last write
+        let started = Instant::now();
+        let sentinel_sql = format!(
+            "SELECT COUNT(*) AS row_count, MAX(updated_at) AS last_write FROM \"{}\".\"{}\"",
+            tenant.schema_name, self.sentinel_table
+        );
+        match sqlx::query(&sentinel_sql).fetch_one(&self.pool).await {
+            Ok(row) => {
+                report.sentinel_rows = row.try_get("row_count").ok();
+                report.last_write_at = row.try_get("last_write").ok().flatten();
+                report.checks.push(CheckResult::new("sentinel", HealthStatus::Ok, started, None));
+            }
+            Err(e) => report.checks.push(CheckResult::new("sentinel", HealthStatus::Failed, started, Some(e.to_string()))),
+        }
+
+        // User activity during the last 24 hours
+        let started = Instant::now();
+        let activity_sql = format!(
+            "SELECT COUNT(*) FROM \"{}\".users WHERE last_login_at > NOW() - INTERVAL '24 hours'",
+            tenant.schema_name
+        );
+        match sqlx::query_scalar::<_, i64>(&activity_sql).fetch_one(&self.pool).await {
+            Ok(count) => {
+                report.active_users_24h = Some(count);
+                report.checks.push(CheckResult::new("activity", HealthStatus::Ok, started, None));
+            }
+            Err(e) => report.checks.push(CheckResult::new("activity", HealthStatus::Failed, started, Some(e.to_string()))),
+        }
+
+        // Provisioning steps that never completed
+        let started = Instant::now();
+        match sqlx::query_scalar::<_, String>(
+            "SELECT step FROM public.tenant_provisioning_steps WHERE tenant_id = $1 AND status <> 'complete' ORDER BY started_at",
+        )
+        .bind(tenant.id)
+        .fetch_all(&self.pool)
+        .await
+        {
+            Ok(steps) => {
+                let status = if steps.is_empty() { HealthStatus::Ok } else { HealthStatus::Degraded };
+                let detail = (!steps.is_empty()).then(|| format!("{} provisioning step(s) incomplete", steps.len()));
+                report.stuck_provisioning_steps = steps;
+                report.checks.push(CheckResult::new("provisioning", status, started, detail));
+            }
+            Err(e) => report.checks.push(CheckResult::new("provisioning", HealthStatus::Failed, started, Some(e.to_string()))),
+        }
+
+        report.checked_at = Utc::now();
+        report.finalize()
+    }
+}
+
+/// Runs tenant probes concurrently, caches results and counts unhealthy tenants
+pub struct TenantHealthMonitor {
+    probe: Arc<dyn TenantProbe>,
+    config: TenantHealthConfig,
+    expected_schema_version: i64,
+    cache: RwLock<Option<(Instant, Vec<TenantHealthReport>)>>,
+    unhealthy_total: IntCounterVec,
+}
+
+impl TenantHealthMonitor {
+    pub fn new(
+        probe: Arc<dyn TenantProbe>,
+        config: TenantHealthConfig,
+        expected_schema_version: i64,
+        namespace: &str,
+    ) -> Result<Self, prometheus::Error> {
+        let unhealthy_total = IntCounterVec::new(
+            Opts::new(
+                format!("{}_tenant_health_unhealthy_total", namespace),
+                "Number of tenant health checks that returned a non-OK status",
+            ),
+            &["tenant_id", "status"],
+        )?;
+
+        Ok(Self {
+            probe,
+            config,
+            expected_schema_version,
+            cache: RwLock::new(None),
+            unhealthy_total,
+        })
+    }
+
+    /// Counter to register with the metrics registry
+    pub fn unhealthy_counter(&self) -> IntCounterVec {
+        self.unhealthy_total.clone()
+    }
+
+    /// Health of all active tenants, served from cache unless `refresh` is set or the cache expired
+    pub async fn check_all(&self, refresh: bool) -> Result<TenantHealthSummary, sqlx::Error> {
+        let ttl = Duration::from_secs(self.config.cache_ttl_seconds);
+        if !refresh {
+            if let Some((at, reports)) = self.cache.read().await.as_ref() {
+                if at.elapsed() < ttl {
+                    return Ok(Self::summarize(reports.clone(), true));
+                }
+            }
+        }
+
+        let tenants = self.probe.list_tenants().await?;
+        let reports = self.run_checks(tenants).await;
+        *self.cache.write().await = Some((Instant::now(), reports.clone()));
+        Ok(Self::summarize(reports, false))
+    }
+
+    /// Fresh health check for a single tenant
+    pub async fn check_one(&self, tenant_id: Uuid) -> Result<Option<TenantHealthReport>, sqlx::Error> {
+        let tenant = self
+            .probe
+            .list_tenants()
+            .await?
+            .into_iter()
+            .find(|t| t.id == tenant_id);
+
+        match tenant {
+            Some(tenant) => Ok(self.run_checks(vec![tenant]).await.into_iter().next()),
+            None => Ok(None),
+        }
+    }
+
+    async fn run_checks(&self, tenants: Vec<TenantRecord>) -> Vec<TenantHealthReport> {
+        let semaphore = Arc::new(Semaphore::new(self.config.max_concurrency.max(1)));
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(self.config.check_timeout_seconds);
+        let mut pending: HashMap<Uuid, TenantRecord> = HashMap::new();
+        let mut tasks = JoinSet::new();
+
+        for tenant in tenants {
+            pending.insert(tenant.id, tenant.clone());
+            let probe = self.probe.clone();
+            let semaphore = semaphore.clone();
+            let expected = self.expected_schema_version;
+            tasks.spawn(async move {
+                let _permit = semaphore.acquire_owned().await.ok();
+                probe.probe(&tenant, expected).await
+            });
+        }
+
+        let mut reports = Vec::with_capacity(pending.len());
+        loop {
+            match tokio::time::timeout_at(deadline, tasks.join_next()).await {
+                Ok(Some(Ok(report))) => {
+                    pending.remove(&report.tenant_id);
+                    reports.push(report);
+                }
+                Ok(Some(Err(e))) => warn!("Tenant health probe task failed: {}", e),
+                Ok(None) => break,
+                Err(_) => {
+                    warn!("Tenant health checks timed out with {} tenant(s) pending", pending.len());
+                    tasks.abort_all();
+                    break;
+                }
+            }
+        }
+
+        // Tenants whose probe panicked or did not finish in time
+        for tenant in pending.values() {
+            reports.push(TenantHealthReport::unavailable(
+                tenant,
+                self.expected_schema_version,
+                HealthStatus::Timeout,
+                "Health check did not complete in time",
+            ));
+        }
+
+        for report in reports.iter().filter(|r| !r.status.is_ok()) {
+            self.unhealthy_total
+                .with_label_values(&[&report.tenant_id.to_string(), report.status.as_str()])
+                .inc();
+        }
+
+        reports.sort_by(|a, b| a.tenant_name.cmp(&b.tenant_name));
+        reports
+    }
+
+    fn summarize(tenants: Vec<TenantHealthReport>, cached: bool) -> TenantHealthSummary {
+        let healthy = tenants.iter().filter(|t| t.status.is_ok()).count();
+        TenantHealthSummary {
+            total: tenants.len(),
+            healthy,
+            unhealthy: tenants.len() - healthy,
+            cached,
+            generated_at: Utc::now(),
+            tenants,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FakeProbe {
+        tenants: Vec<TenantRecord>,
+        broken_schema: &'static str,
+        slow_schema: Option<&'static str>,
+    }
+
+    #[async_trait]
+    impl TenantProbe for FakeProbe {
+        async fn list_tenants(&self) -> Result<Vec<TenantRecord>, sqlx::Error> {
+            Ok(self.tenants.clone())
+        }
+
+        async fn probe(&self, tenant: &TenantRecord, expected: i64) -> TenantHealthReport {
+            if Some(tenant.schema_name.as_str()) == self.slow_schema {
+                tokio::time::sleep(Duration::from_secs(30)).await;
+            }
+
+            let mut report = TenantHealthReport::new(tenant, expected);
+            let started = Instant::now();
+            if tenant.schema_name == self.broken_schema {
+                report.checks.push(CheckResult::new(
+                    "sentinel",
+                    HealthStatus::Failed,
+                    started,
+                    Some("relation \"users\" does not exist".to_string()),
+                ));
+            } else {
+                report.schema_version = Some(expected);
+                report.checks.push(CheckResult::new("sentinel", HealthStatus::Ok, started, None));
+            }
+            report.finalize()
+        }
+    }
+
+    fn tenants(n: usize) -> Vec<TenantRecord> {
+        (0..n)
+            .map(|i| TenantRecord {
+                id: Uuid::new_v4(),
+                name: format!("tenant {}", i),
+                schema_name: format!("acme_{}", i),
+            })
+            .collect()
+    }
+
+    fn monitor(probe: FakeProbe, timeout_seconds: u64) -> TenantHealthMonitor {
+        let config = TenantHealthConfig {
+            max_concurrency: 2,
+            check_timeout_seconds: timeout_seconds,
+            cache_ttl_seconds: 60,
+            sentinel_table: "users".to_string(),
+        };
+        TenantHealthMonitor::new(Arc::new(probe), config, 3, "test").unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_one_broken_schema_among_healthy() {
+        let monitor = monitor(FakeProbe { tenants: tenants(5), broken_schema: "acme_3", slow_schema: None }, 5);
+
+        let summary = monitor.check_all(false).await.unwrap();
+
+        assert_eq!(summary.total, 5);
+        assert_eq!(summary.healthy, 4);
+        assert_eq!(summary.unhealthy, 1);
+        let broken = summary.tenants.iter().find(|t| !t.status.is_ok()).unwrap();
+        assert_eq!(broken.schema_name, "acme_3");
+        assert_eq!(broken.status, HealthStatus::Failed);
+
+        let counter = monitor.unhealthy_counter();
+        assert_eq!(counter.with_label_values(&[&broken.tenant_id.to_string(), "failed"]).get(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_results_are_cached_until_refresh() {
+        let monitor = monitor(FakeProbe { tenants: tenants(3), broken_schema: "none", slow_schema: None }, 5);
+
+        assert!(!monitor.check_all(false).await.unwrap().cached);
+        assert!(monitor.check_all(false).await.unwrap().cached);
+        assert!(!monitor.check_all(true).await.unwrap().cached);
+    }
+
+    #[tokio::test]
+    async fn test_overall_timeout_marks_pending_tenants() {
+        let monitor = monitor(FakeProbe { tenants: tenants(3), broken_schema: "none", slow_schema: Some("acme_1") }, 1);
+
+        let summary = monitor.check_all(true).await.unwrap();
+
+        assert_eq!(summary.total, 3);
+        let slow = summary.tenants.iter().find(|t| t.schema_name == "acme_1").unwrap();
+        assert_eq!(slow.status, HealthStatus::Timeout);
+        assert_eq!(summary.healthy, 2);
+    }
+
+    #[tokio::test]
+    async fn test_check_one_unknown_tenant() {
+        let monitor = monitor(FakeProbe { tenants: tenants(2), broken_schema: "none", slow_schema: None }, 5);
+        assert!(monitor.check_one(Uuid::new_v4()).await.unwrap().is_none());
+    }
+}
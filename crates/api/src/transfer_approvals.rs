@@ -0,0 +1,274 @@
+//! # Transfer Approval Notifications
+//!
+//! Glue between stock transfer approvals and the notification pipeline:
+//!
+//! - a transfer that starts waiting for approval notifies every user holding
+//!   `inventory:approve_transfers`, except the user who created it
+//! - a decision notifies the creator
+//! - approvals still undecided after `transfer_approvals.escalate_after_hours`
+//!   are escalated once with a critical notification to the approvers and the
+//!   holders of `transfer_approvals.escalation_permission`
+//!
+//! Notifications use the `transfer_approval` category, so users choose between
+//! immediate emails and the daily digest like for any other category;
+//! escalations are critical and always emailed right away.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use erp_core::{DatabasePool, Error, ErrorCode, Result, TenantContext, TenantId, TransferApprovalConfig};
+use erp_master_data::inventory::{
+    ApprovalDecision, StockTransferRepository, TransferApproval, TransferRecord, TRANSFER_APPROVAL_PERMISSION,
+};
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::notification_digest::{Notification, NotificationService, NotificationSeverity};
+
+/// Notification type stored in `notification_preferences`
+pub const TRANSFER_APPROVAL: &str = "transfer_approval";
+
+/// Who holds a permission in a tenant
+#[async_trait]
+pub trait PermissionHolders: Send + Sync {
+    async fn users_with_permission(&self, tenant_id: Uuid, permission: &str) -> Result<Vec<Uuid>>;
+}
+
+/// Roles and their permissions from the tenant schema
+pub struct PostgresPermissionHolders {
+    db: DatabasePool,
+}
+
+impl PostgresPermissionHolders {
+    pub fn new(db: DatabasePool) -> Self {
+        Self { db }
+    }
+}
+
+#[async_trait]
+impl PermissionHolders for PostgresPermissionHolders {
+    async fn users_with_permission(&self, tenant_id: Uuid, permission: &str) -> Result<Vec<Uuid>> {
+        let Some(schema_name) = sqlx::query_scalar::<_, String>(
+            "SELECT schema_name FROM public.tenants WHERE id = $1 AND status = 'active'",
+        )
+        .bind(tenant_id)
+        .fetch_optional(&self.db.main_pool)
+        .await?
+        else {
+            return Ok(Vec::new());
+        };
+
+        let tenant = TenantContext {
+            tenant_id: TenantId(tenant_id),
+            schema_name,
+        };
+        let pool = self.db.get_tenant_pool(&tenant).await?;
+        let users = sqlx::query_scalar(
+            "SELECT DISTINCT ur.user_id FROM user_roles ur \
+             JOIN role_permissions rp ON rp.role_id = ur.role_id \
+             JOIN permissions p ON p.id = rp.permission_id \
+             WHERE p.resource || ':' || p.action = $1 \
+             ORDER BY ur.user_id",
+        )
+        .bind(permission)
+        .fetch_all(pool.get())
+        .await?;
+        Ok(users)
+    }
+}
+
+/// Everyone in `groups` except the user who asked for the approval, once each
+pub fn recipients(groups: &[Vec<Uuid>], requested_by: Uuid) -> Vec<Uuid> {
+    groups
+        .iter()
+        .flatten()
+        .copied()
+        .filter(|user_id| *user_id != requested_by)
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+fn transfer_error(e: erp_master_data::MasterDataError) -> Error {
+    Error::new(ErrorCode::DatabaseError, e.to_string())
+}
+
+fn link(transfer_id: Uuid) -> String {
+    format!("/inventory/transfers/{}", transfer_id)
+}
+
+/// Approval notifications and escalation of overdue approvals
+pub struct TransferApprovalNotifier {
+    transfers: Arc<dyn StockTransferRepository>,
+    holders: Arc<dyn PermissionHolders>,
+    notifications: Arc<NotificationService>,
+    config: TransferApprovalConfig,
+}
+
+impl TransferApprovalNotifier {
+    pub fn new(
+        transfers: Arc<dyn StockTransferRepository>,
+        holders: Arc<dyn PermissionHolders>,
+        notifications: Arc<NotificationService>,
+        config: TransferApprovalConfig,
+    ) -> Self {
+        Self {
+            transfers,
+            holders,
+            notifications,
+            config,
+        }
+    }
+
+    async fn send(&self, approval: &TransferApproval, user_ids: &[Uuid], severity: NotificationSeverity, title: &str, body: String) {
+        let now = Utc::now();
+        for user_id in user_ids {
+            let notification = Notification {
+                tenant_id: approval.tenant_id,
+                user_id: *user_id,
+                category: TRANSFER_APPROVAL.to_string(),
+                severity,
+                title: title.to_string(),
+                body: Some(body.clone()),
+                location_id: None,
+                location_name: None,
+                customer_id: None,
+                customer_name: None,
+                link: Some(link(approval.transfer_id)),
+                created_at: now,
+            };
+            if let Err(e) = self.notifications.notify(&notification).await {
+                warn!("Failed to notify user {} about transfer {}: {}", user_id, approval.transfer_id, e);
+            }
+        }
+    }
+
+    /// Tell the approvers a new transfer waits for them; failures are logged, never returned
+    pub async fn transfer_pending(&self, record: &TransferRecord) {
+        let Some(approval) = &record.approval else {
+            return;
+        };
+        let approvers = match self
+            .holders
+            .users_with_permission(approval.tenant_id, TRANSFER_APPROVAL_PERMISSION)
+            .await
+        {
+            Ok(approvers) => approvers,
+            Err(e) => {
+                warn!("Failed to look up approvers for transfer {}: {}", approval.transfer_id, e);
+                return;
+            }
+        };
+
+        let body = format!(
+            "{} units worth {:.2} wait for approval: {}",
+            record.transfer.quantity,
+            approval.transfer_value,
+            approval.reasons.join("; ")
+        );
+        let user_ids = recipients(&[approvers], approval.requested_by);
+        self.send(approval, &user_ids, NotificationSeverity::Warning, "Stock transfer awaiting approval", body)
+            .await;
+    }
+
+    /// Tell the creator how their transfer was decided
+    pub async fn transfer_decided(&self, record: &TransferRecord) {
+        let Some(approval) = &record.approval else {
+            return;
+        };
+        let Some(decision) = approval.decision else {
+            return;
+        };
+
+        let title = match decision {
+            ApprovalDecision::Approved => "Stock transfer approved",
+            ApprovalDecision::Rejected => "Stock transfer rejected",
+        };
+        let body = match &approval.comment {
+            Some(comment) => format!("Transfer {} was {}: {}", approval.transfer_id, decision.as_str(), comment),
+            None => format!("Transfer {} was {}", approval.transfer_id, decision.as_str()),
+        };
+        self.send(approval, &[approval.requested_by], NotificationSeverity::Info, title, body)
+            .await;
+    }
+
+    /// Escalate every approval undecided for too long; returns the number escalated
+    pub async fn escalate_overdue(&self, now: DateTime<Utc>) -> Result<usize> {
+        let escalate_after = Duration::hours(self.config.escalate_after_hours.max(1));
+        let overdue = self
+            .transfers
+            .list_overdue_approvals(now - escalate_after)
+            .await
+            .map_err(transfer_error)?;
+
+        let mut escalated = 0;
+        for approval in overdue.iter().filter(|approval| approval.needs_escalation(now, escalate_after)) {
+            let approvers = self
+                .holders
+                .users_with_permission(approval.tenant_id, TRANSFER_APPROVAL_PERMISSION)
+                .await?;
+            let supervisors = self
+                .holders
+                .users_with_permission(approval.tenant_id, &self.config.escalation_permission)
+                .await?;
+
+            let hours = (now - approval.requested_at).num_hours();
+            let body = format!(
+                "Transfer {} worth {:.2} has waited {} hours for approval: {}",
+                approval.transfer_id,
+                approval.transfer_value,
+                hours,
+                approval.reasons.join("; ")
+            );
+            let user_ids = recipients(&[approvers, supervisors], approval.requested_by);
+            self.send(approval, &user_ids, NotificationSeverity::Critical, "Overdue stock transfer approval", body)
+                .await;
+
+            self.transfers
+                .mark_escalated(approval.tenant_id, approval.transfer_id, now)
+                .await
+                .map_err(transfer_error)?;
+            escalated += 1;
+        }
+        Ok(escalated)
+    }
+
+    /// Escalate overdue approvals every `transfer_approvals.poll_interval_seconds` in the background
+    pub fn spawn(self: &Arc<Self>) -> Option<tokio::task::JoinHandle<()>> {
+        if !self.config.enabled {
+            return None;
+        }
+
+        let notifier = Arc::clone(self);
+        Some(tokio::spawn(async move {
+            let mut ticker =
+                tokio::time::interval(std::time::Duration::from_secs(notifier.config.poll_interval_seconds.max(60)));
+            loop {
+                ticker.tick().await;
+                match notifier.escalate_overdue(Utc::now()).await {
+                    Ok(0) => {}
+                    Ok(escalated) => info!("Escalated {} overdue transfer approvals", escalated),
+                    Err(e) => warn!("Transfer approval escalation failed: {}", e),
+                }
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recipients_exclude_requester_once_each() {
+        let (clerk, manager, admin) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+
+        let users = recipients(&[vec![clerk, manager], vec![manager, admin]], clerk);
+        let mut expected = vec![manager, admin];
+        expected.sort();
+        assert_eq!(users, expected);
+
+        assert!(recipients(&[vec![clerk]], clerk).is_empty());
+    }
+}
@@ -0,0 +1,1193 @@
+//! # Tenant Webhooks
+//!
+//! Tenants subscribe endpoints to the event streams of their data under
+//! `/api/v1/webhooks`: customer events, inventory events (stockouts, stock
+//! falling to the reorder point, completed transfers) and security-relevant
+//! auth events (users created, roles assigned or revoked, lockouts after
+//! failed logins), so they can feed their SIEM and ops tooling.
+//!
+//! A subscription names the sources it wants and, optionally, single event
+//! types as `source.type`; a source without any listed type is taken whole.
+//! The dispatcher reads every source through the [`EventSource`] trait after
+//! the source's high-water mark in `public.webhook_source_marks` and queues
+//! one delivery per matching subscription, advancing the mark in the same
+//! transaction. Queuing is at-least-once: deliveries are unique on
+//! `(subscription, source, source_id)`, so a replayed source queues nothing
+//! twice. Events younger than [`SETTLE_SECS`] wait for the next poll.
+//!
+//! Deliveries are numbered per `(subscription, source)` and that lane is sent
+//! in order: only its oldest pending delivery is ever attempted, so a failing
+//! endpoint holds up the lane and nothing else. Failed attempts are retried
+//! with a doubling backoff; after `webhooks.max_attempts` the delivery is
+//! marked failed and the lane moves on.
+//!
+//! Every delivery is the same [`Envelope`] (source, type, tenant,
+//! occurred_at, sequence, data) signed with the subscription's secret, see
+//! [`sign`]. The `data` of each event type is documented in
+//! [`sources::EVENT_TYPES`] and published as a JSON Schema of the envelope
+//! under `docs/api/webhooks/`.
+
+pub mod sources;
+pub mod store;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use erp_core::retention::{RetentionAction, RetentionCategory, RetentionHandler};
+use erp_core::{Error, ErrorCode, Result, WebhooksConfig};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use std::sync::Arc;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::activity::HighWaterMark;
+pub use sources::{event_type, EventTypeDoc, EVENT_TYPES};
+pub use store::{OutboundTransport, PostgresWebhookStore};
+
+/// Events younger than this are not queued yet
+pub const SETTLE_SECS: i64 = 5;
+
+/// Header with the timestamp and signature of a delivery
+pub const SIGNATURE_HEADER: &str = "X-ERP-Signature";
+
+/// Outbound integration deliveries are posted with
+pub const WEBHOOK_INTEGRATION: &str = "webhooks";
+
+/// Longest error kept on a delivery, in characters
+const MAX_ERROR_CHARS: usize = 500;
+
+/// An event stream subscriptions can take
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookSource {
+    Customer,
+    Inventory,
+    Auth,
+}
+
+impl WebhookSource {
+    pub const ALL: [WebhookSource; 3] = [WebhookSource::Customer, WebhookSource::Inventory, WebhookSource::Auth];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            WebhookSource::Customer => "customer",
+            WebhookSource::Inventory => "inventory",
+            WebhookSource::Auth => "auth",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "customer" => Some(WebhookSource::Customer),
+            "inventory" => Some(WebhookSource::Inventory),
+            "auth" => Some(WebhookSource::Auth),
+            _ => None,
+        }
+    }
+}
+
+/// An event as read from a source
+#[derive(Debug, Clone)]
+pub struct StreamEvent {
+    pub source: WebhookSource,
+    pub source_id: String,
+    /// Ordering column of the source
+    pub position: DateTime<Utc>,
+    /// None for platform events outside any tenant
+    pub tenant_id: Option<Uuid>,
+    /// One of the source's types in [`EVENT_TYPES`]
+    pub event_type: String,
+    pub occurred_at: DateTime<Utc>,
+    pub data: Value,
+}
+
+/// One event stream as the dispatcher reads it
+#[async_trait]
+pub trait EventSource: Send + Sync {
+    fn source(&self) -> WebhookSource;
+
+    /// Up to `limit` events after `after` that are older than `settled_before`, in mark order
+    async fn read_after(
+        &self,
+        after: Option<&HighWaterMark>,
+        settled_before: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<StreamEvent>>;
+}
+
+/// What a delivery posts
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Envelope {
+    /// `{source}:{source_id}`, the same on every attempt
+    pub id: String,
+    pub source: WebhookSource,
+    #[serde(rename = "type")]
+    pub event_type: String,
+    pub tenant_id: Uuid,
+    pub occurred_at: DateTime<Utc>,
+    /// Position in the subscription's lane of this source, from 1 without gaps
+    pub sequence: i64,
+    pub data: Value,
+}
+
+/// JSON Schema of the envelope of one event type
+pub fn envelope_schema(doc: &EventTypeDoc) -> Value {
+    json!({
+        "description": doc.description,
+        "type": "object",
+        "required": ["id", "source", "type", "tenant_id", "occurred_at", "sequence", "data"],
+        "properties": {
+            "id": { "type": "string" },
+            "source": { "const": doc.source.as_str() },
+            "type": { "const": doc.event_type },
+            "tenant_id": { "type": "string", "format": "uuid" },
+            "occurred_at": { "type": "string", "format": "date-time" },
+            "sequence": { "type": "integer", "minimum": 1 },
+            "data": (doc.data_schema)()
+        }
+    })
+}
+
+/// An envelope of one event type as delivered, with the type's example `data`
+pub fn example_envelope(doc: &EventTypeDoc) -> Value {
+    json!({
+        "id": match doc.source {
+            WebhookSource::Inventory => "inventory:1042".to_string(),
+            source => format!("{}:8d1f5c2e-6b7a-4c3d-9e0f-1a2b3c4d5e6f", source.as_str()),
+        },
+        "source": doc.source,
+        "type": doc.event_type,
+        "tenant_id": "9a8b7c6d-5e4f-4a3b-9c2d-1e0f9a8b7c6d",
+        "occurred_at": "2026-10-17T08:30:00Z",
+        "sequence": 42,
+        "data": (doc.example)()
+    })
+}
+
+type HmacSha256 = Hmac<Sha256>;
+
+fn signature(secret: &str, timestamp: i64, body: &str) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body.as_bytes());
+    mac
+}
+
+/// Value of [`SIGNATURE_HEADER`]: `t={unix seconds},v1={hex HMAC-SHA256 of "{t}.{body}"}`
+pub fn sign(secret: &str, timestamp: i64, body: &str) -> String {
+    let digest = signature(secret, timestamp, body).finalize().into_bytes();
+    format!("t={},v1={}", timestamp, hex::encode(digest))
+}
+
+/// `whsec_` and 32 random bytes in hex
+fn new_secret() -> String {
+    let mut bytes = [0u8; 32];
+    rand::rngs::OsRng.fill_bytes(&mut bytes);
+    format!("whsec_{}", hex::encode(bytes))
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Subscription {
+    pub id: Uuid,
+    pub tenant_id: Uuid,
+    pub name: String,
+    pub url: String,
+    /// Shown once, when the subscription is created or the secret rotated
+    #[serde(skip_serializing)]
+    pub secret: String,
+    pub sources: Vec<WebhookSource>,
+    /// `source.type`; a source without any listed type is taken whole
+    pub event_types: Vec<String>,
+    pub active: bool,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Subscription {
+    /// Whether `event` is delivered to this subscription
+    pub fn wants(&self, event: &StreamEvent) -> bool {
+        if !self.active || event.tenant_id != Some(self.tenant_id) || !self.sources.contains(&event.source) {
+            return false;
+        }
+        let prefix = format!("{}.", event.source.as_str());
+        let mut listed = self.event_types.iter().filter_map(|t| t.strip_prefix(&prefix)).peekable();
+        listed.peek().is_none() || listed.any(|t| t == event.event_type)
+    }
+}
+
+/// Body of a subscription create or update
+#[derive(Debug, Clone, Deserialize)]
+pub struct SubscriptionRequest {
+    pub name: String,
+    pub url: String,
+    pub sources: Vec<String>,
+    #[serde(default)]
+    pub event_types: Vec<String>,
+    pub active: Option<bool>,
+}
+
+fn invalid(message: impl Into<String>) -> Error {
+    Error::new(ErrorCode::ValidationFailed, message)
+}
+
+impl SubscriptionRequest {
+    /// Sources and event types of the request, checked against the catalog
+    pub fn validate(&self) -> Result<(Vec<WebhookSource>, Vec<String>)> {
+        if self.name.trim().is_empty() || self.name.chars().count() > 200 {
+            return Err(invalid("Name must be 1 to 200 characters"));
+        }
+        let host = self.url.strip_prefix("https://").map(|rest| rest.split(['/', '?', '#']).next().unwrap_or(""));
+        if host.is_none_or(str::is_empty) {
+            return Err(invalid("URL must be an https:// address"));
+        }
+
+        let mut sources = Vec::new();
+        for name in &self.sources {
+            let source = WebhookSource::parse(name).ok_or_else(|| invalid(format!("Unknown event source '{}'", name)))?;
+            if !sources.contains(&source) {
+                sources.push(source);
+            }
+        }
+        if sources.is_empty() {
+            return Err(invalid("At least one event source is required"));
+        }
+
+        let mut event_types = Vec::new();
+        for name in &self.event_types {
+            let known = name
+                .split_once('.')
+                .and_then(|(source, event)| event_type(WebhookSource::parse(source)?, event));
+            let Some(doc) = known else {
+                return Err(invalid(format!("Unknown event type '{}'", name)));
+            };
+            if !sources.contains(&doc.source) {
+                return Err(invalid(format!("Event type '{}' needs source '{}'", name, doc.source.as_str())));
+            }
+            if !event_types.contains(name) {
+                event_types.push(name.clone());
+            }
+        }
+        Ok((sources, event_types))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryStatus {
+    Pending,
+    Delivered,
+    Failed,
+}
+
+impl DeliveryStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DeliveryStatus::Pending => "pending",
+            DeliveryStatus::Delivered => "delivered",
+            DeliveryStatus::Failed => "failed",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "pending" => Some(DeliveryStatus::Pending),
+            "delivered" => Some(DeliveryStatus::Delivered),
+            "failed" => Some(DeliveryStatus::Failed),
+            _ => None,
+        }
+    }
+}
+
+/// An event queued for a subscription, before it is numbered
+#[derive(Debug, Clone)]
+pub struct NewDelivery {
+    pub subscription_id: Uuid,
+    pub tenant_id: Uuid,
+    pub source: WebhookSource,
+    pub event_type: String,
+    pub source_id: String,
+    pub occurred_at: DateTime<Utc>,
+    pub data: Value,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Delivery {
+    pub id: i64,
+    pub subscription_id: Uuid,
+    pub tenant_id: Uuid,
+    pub source: WebhookSource,
+    pub event_type: String,
+    pub source_id: String,
+    pub sequence: i64,
+    pub occurred_at: DateTime<Utc>,
+    pub data: Value,
+    pub status: DeliveryStatus,
+    pub attempts: i32,
+    pub next_attempt_at: DateTime<Utc>,
+    pub last_error: Option<String>,
+    pub response_status: Option<i32>,
+    pub created_at: DateTime<Utc>,
+    pub delivered_at: Option<DateTime<Utc>>,
+}
+
+impl Delivery {
+    pub fn envelope(&self) -> Envelope {
+        Envelope {
+            id: format!("{}:{}", self.source.as_str(), self.source_id),
+            source: self.source,
+            event_type: self.event_type.clone(),
+            tenant_id: self.tenant_id,
+            occurred_at: self.occurred_at,
+            sequence: self.sequence,
+            data: self.data.clone(),
+        }
+    }
+}
+
+/// The oldest pending delivery of a lane, with where it goes
+#[derive(Debug, Clone)]
+pub struct DueDelivery {
+    pub delivery: Delivery,
+    pub url: String,
+    pub secret: String,
+}
+
+/// Result of one attempt
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttemptOutcome {
+    Delivered { response_status: u16 },
+    Retry { error: String, response_status: Option<u16>, next_attempt_at: DateTime<Utc> },
+    Failed { error: String, response_status: Option<u16> },
+}
+
+/// Filters of the delivery list
+#[derive(Debug, Clone, Default)]
+pub struct DeliveryQuery {
+    pub source: Option<WebhookSource>,
+    pub event_type: Option<String>,
+    pub status: Option<DeliveryStatus>,
+    pub subscription_id: Option<Uuid>,
+    /// Deliveries with a smaller id come next
+    pub before_id: Option<i64>,
+}
+
+/// One page of deliveries, newest first
+#[derive(Debug, Clone, Serialize)]
+pub struct DeliveryPage {
+    pub deliveries: Vec<Delivery>,
+    /// `before` of the next page
+    pub next_before: Option<i64>,
+    pub has_more: bool,
+}
+
+/// Subscriptions, queued deliveries and source marks
+#[async_trait]
+pub trait WebhookStore: Send + Sync {
+    async fn source_mark(&self, source: WebhookSource) -> Result<Option<HighWaterMark>>;
+
+    /// Active subscriptions of every tenant that take events of `source`
+    async fn subscribers(&self, source: WebhookSource) -> Result<Vec<Subscription>>;
+
+    /// Queue deliveries not queued before, numbered after the last of their
+    /// lane, and move the source's mark, atomically; returns deliveries queued
+    async fn enqueue(&self, source: WebhookSource, deliveries: &[NewDelivery], mark: &HighWaterMark) -> Result<u64>;
+
+    /// Oldest pending delivery of up to `limit` lanes of active subscriptions, if its attempt is due
+    async fn due(&self, now: DateTime<Utc>, limit: i64) -> Result<Vec<DueDelivery>>;
+
+    async fn record_attempt(&self, delivery_id: i64, outcome: &AttemptOutcome, now: DateTime<Utc>) -> Result<()>;
+
+    async fn subscriptions(&self, tenant_id: Uuid) -> Result<Vec<Subscription>>;
+
+    async fn subscription(&self, tenant_id: Uuid, id: Uuid) -> Result<Option<Subscription>>;
+
+    /// Insert or replace
+    async fn save_subscription(&self, subscription: &Subscription) -> Result<()>;
+
+    /// Remove it with its deliveries; false if the tenant has no such subscription
+    async fn delete_subscription(&self, tenant_id: Uuid, id: Uuid) -> Result<bool>;
+
+    /// Up to `limit` deliveries of the tenant matching `query`, newest first
+    async fn deliveries(&self, tenant_id: Uuid, query: &DeliveryQuery, limit: i64) -> Result<Vec<Delivery>>;
+
+    /// Delivered or failed deliveries of the tenant created before `cutoff`
+    async fn count_before(&self, tenant_id: Uuid, cutoff: DateTime<Utc>) -> Result<u64>;
+
+    /// Remove up to `limit` of those; returns rows removed
+    async fn delete_before(&self, tenant_id: Uuid, cutoff: DateTime<Utc>, limit: i64) -> Result<u64>;
+}
+
+/// Posts a signed delivery
+#[async_trait]
+pub trait WebhookTransport: Send + Sync {
+    /// The HTTP status the endpoint answered; an error when there was no answer
+    async fn post(&self, url: &str, headers: &[(&'static str, String)], body: String) -> Result<u16>;
+}
+
+/// Finished deliveries as the `webhook_deliveries` retention category
+pub struct WebhookDeliveryRetention {
+    store: Arc<dyn WebhookStore>,
+}
+
+impl WebhookDeliveryRetention {
+    pub fn category(store: Arc<dyn WebhookStore>, config: &WebhooksConfig) -> RetentionCategory {
+        RetentionCategory {
+            name: "webhook_deliveries".to_string(),
+            description: "Delivered and failed webhook deliveries by creation date; pending ones are kept".to_string(),
+            default_days: config.retention_days.max(1),
+            default_action: RetentionAction::Delete,
+            supported_actions: vec![RetentionAction::Delete],
+            handler: Arc::new(Self { store }),
+        }
+    }
+}
+
+#[async_trait]
+impl RetentionHandler for WebhookDeliveryRetention {
+    async fn count_expired(&self, tenant_id: Uuid, cutoff: DateTime<Utc>, _action: RetentionAction) -> Result<u64> {
+        self.store.count_before(tenant_id, cutoff).await
+    }
+
+    async fn enforce_batch(
+        &self,
+        tenant_id: Uuid,
+        cutoff: DateTime<Utc>,
+        _action: RetentionAction,
+        limit: i64,
+    ) -> Result<u64> {
+        self.store.delete_before(tenant_id, cutoff, limit).await
+    }
+}
+
+/// Attempts of one delivery pass
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DeliveryRun {
+    pub delivered: u64,
+    pub retried: u64,
+    pub failed: u64,
+}
+
+impl DeliveryRun {
+    fn attempts(&self) -> u64 {
+        self.delivered + self.retried + self.failed
+    }
+}
+
+/// Queues source events for subscriptions, delivers them and manages subscriptions
+pub struct WebhookService {
+    store: Arc<dyn WebhookStore>,
+    sources: Vec<Arc<dyn EventSource>>,
+    transport: Arc<dyn WebhookTransport>,
+    config: WebhooksConfig,
+}
+
+impl WebhookService {
+    pub fn new(
+        store: Arc<dyn WebhookStore>,
+        sources: Vec<Arc<dyn EventSource>>,
+        transport: Arc<dyn WebhookTransport>,
+        config: WebhooksConfig,
+    ) -> Self {
+        Self {
+            store,
+            sources,
+            transport,
+            config,
+        }
+    }
+
+    pub fn config(&self) -> &WebhooksConfig {
+        &self.config
+    }
+
+    /// Queue everything `source` has after its mark; returns deliveries queued
+    pub async fn fan_out(&self, source: &dyn EventSource, now: DateTime<Utc>) -> Result<u64> {
+        let batch_size = self.config.batch_size.max(1);
+        let settled_before = now - Duration::seconds(SETTLE_SECS);
+        let subscribers = self.store.subscribers(source.source()).await?;
+        let mut mark = self.store.source_mark(source.source()).await?;
+        let mut queued = 0;
+
+        loop {
+            let events = source.read_after(mark.as_ref(), settled_before, batch_size).await?;
+            let Some(last) = events.last() else {
+                break;
+            };
+            let next = HighWaterMark {
+                position: last.position,
+                source_id: last.source_id.clone(),
+            };
+            let deliveries: Vec<NewDelivery> = events
+                .iter()
+                .flat_map(|event| {
+                    subscribers.iter().filter(|s| s.wants(event)).map(|s| NewDelivery {
+                        subscription_id: s.id,
+                        tenant_id: s.tenant_id,
+                        source: event.source,
+                        event_type: event.event_type.clone(),
+                        source_id: event.source_id.clone(),
+                        occurred_at: event.occurred_at,
+                        data: event.data.clone(),
+                    })
+                })
+                .collect();
+            queued += self.store.enqueue(source.source(), &deliveries, &next).await?;
+
+            if (events.len() as i64) < batch_size {
+                break;
+            }
+            mark = Some(next);
+        }
+        Ok(queued)
+    }
+
+    /// Queue from every source; a failing source does not hold up the others
+    pub async fn fan_out_all(&self, now: DateTime<Utc>) -> u64 {
+        let mut queued = 0;
+        for source in &self.sources {
+            match self.fan_out(source.as_ref(), now).await {
+                Ok(count) => queued += count,
+                Err(e) => warn!("Queuing {} webhook events failed: {}", source.source().as_str(), e),
+            }
+        }
+        queued
+    }
+
+    fn backoff(&self, attempts: i32) -> Duration {
+        let base = self.config.retry_backoff_secs.max(1);
+        let exponent = (attempts - 1).clamp(0, 20) as u32;
+        Duration::seconds(base.saturating_mul(1 << exponent).min(self.config.max_backoff_secs.max(base)))
+    }
+
+    async fn attempt(&self, due: &DueDelivery, now: DateTime<Utc>) -> AttemptOutcome {
+        let delivery = &due.delivery;
+        let body = serde_json::to_string(&delivery.envelope()).expect("envelope serializes");
+        let headers = [
+            ("Content-Type", "application/json".to_string()),
+            (SIGNATURE_HEADER, sign(&due.secret, now.timestamp(), &body)),
+            ("X-ERP-Event", format!("{}.{}", delivery.source.as_str(), delivery.event_type)),
+            ("X-ERP-Delivery", delivery.id.to_string()),
+        ];
+
+        let (error, response_status) = match self.transport.post(&due.url, &headers, body).await {
+            Ok(status) if (200..300).contains(&status) => {
+                return AttemptOutcome::Delivered { response_status: status };
+            }
+            Ok(status) => (format!("Endpoint answered {}", status), Some(status)),
+            Err(e) => (e.message.chars().take(MAX_ERROR_CHARS).collect(), None),
+        };
+
+        let attempts = delivery.attempts + 1;
+        if attempts >= self.config.max_attempts.max(1) {
+            AttemptOutcome::Failed { error, response_status }
+        } else {
+            AttemptOutcome::Retry {
+                error,
+                response_status,
+                next_attempt_at: now + self.backoff(attempts),
+            }
+        }
+    }
+
+    /// Attempt the head of every lane that is due, once
+    pub async fn deliver_due(&self, now: DateTime<Utc>) -> Result<DeliveryRun> {
+        let mut run = DeliveryRun::default();
+        for due in self.store.due(now, self.config.batch_size.max(1)).await? {
+            let outcome = self.attempt(&due, now).await;
+            match &outcome {
+                AttemptOutcome::Delivered { .. } => run.delivered += 1,
+                AttemptOutcome::Retry { .. } => run.retried += 1,
+                AttemptOutcome::Failed { error, .. } => {
+                    warn!(
+                        "Giving up webhook delivery {} of subscription {}: {}",
+                        due.delivery.id, due.delivery.subscription_id, error
+                    );
+                    run.failed += 1;
+                }
+            }
+            self.store.record_attempt(due.delivery.id, &outcome, now).await?;
+        }
+        Ok(run)
+    }
+
+    /// Queue new events, then deliver until every lane waits for a retry or
+    /// is empty, with at most `batch_size` attempts
+    pub async fn run_once(&self, now: DateTime<Utc>) -> Result<DeliveryRun> {
+        self.fan_out_all(now).await;
+        let mut total = DeliveryRun::default();
+        loop {
+            let run = self.deliver_due(now).await?;
+            total.delivered += run.delivered;
+            total.retried += run.retried;
+            total.failed += run.failed;
+            if run.attempts() == 0 || total.attempts() >= self.config.batch_size.max(1) as u64 {
+                return Ok(total);
+            }
+        }
+    }
+
+    /// Poll the sources and deliver every `webhooks.poll_interval_ms` in the background
+    pub fn spawn(self: &Arc<Self>) -> Option<tokio::task::JoinHandle<()>> {
+        if !self.config.enabled {
+            return None;
+        }
+        let service = Arc::clone(self);
+        Some(tokio::spawn(async move {
+            let mut ticker =
+                tokio::time::interval(std::time::Duration::from_millis(service.config.poll_interval_ms.max(100)));
+            loop {
+                ticker.tick().await;
+                match service.run_once(Utc::now()).await {
+                    Ok(run) if run.attempts() > 0 => info!(
+                        "Webhooks: {} delivered, {} to retry, {} given up",
+                        run.delivered, run.retried, run.failed
+                    ),
+                    Ok(_) => {}
+                    Err(e) => warn!("Webhook delivery failed: {}", e),
+                }
+            }
+        }))
+    }
+
+    pub async fn subscriptions(&self, tenant_id: Uuid) -> Result<Vec<Subscription>> {
+        self.store.subscriptions(tenant_id).await
+    }
+
+    async fn existing(&self, tenant_id: Uuid, id: Uuid) -> Result<Subscription> {
+        self.store
+            .subscription(tenant_id, id)
+            .await?
+            .ok_or_else(|| Error::new(ErrorCode::NotFound, format!("Webhook subscription {} not found", id)))
+    }
+
+    /// A new subscription with a fresh secret; events already in the sources are not delivered to it
+    pub async fn create_subscription(
+        &self,
+        tenant_id: Uuid,
+        user_id: Option<Uuid>,
+        request: &SubscriptionRequest,
+    ) -> Result<Subscription> {
+        let (sources, event_types) = request.validate()?;
+        let now = Utc::now();
+        let subscription = Subscription {
+            id: Uuid::new_v4(),
+            tenant_id,
+            name: request.name.trim().to_string(),
+            url: request.url.clone(),
+            secret: new_secret(),
+            sources,
+            event_types,
+            active: request.active.unwrap_or(true),
+            created_by: user_id,
+            created_at: now,
+            updated_at: now,
+        };
+        self.store.save_subscription(&subscription).await?;
+        Ok(subscription)
+    }
+
+    pub async fn update_subscription(
+        &self,
+        tenant_id: Uuid,
+        id: Uuid,
+        request: &SubscriptionRequest,
+    ) -> Result<Subscription> {
+        let (sources, event_types) = request.validate()?;
+        let mut subscription = self.existing(tenant_id, id).await?;
+        subscription.name = request.name.trim().to_string();
+        subscription.url = request.url.clone();
+        subscription.sources = sources;
+        subscription.event_types = event_types;
+        subscription.active = request.active.unwrap_or(subscription.active);
+        subscription.updated_at = Utc::now();
+        self.store.save_subscription(&subscription).await?;
+        Ok(subscription)
+    }
+
+    /// A new secret; deliveries are signed with it from the next attempt on
+    pub async fn rotate_secret(&self, tenant_id: Uuid, id: Uuid) -> Result<Subscription> {
+        let mut subscription = self.existing(tenant_id, id).await?;
+        subscription.secret = new_secret();
+        subscription.updated_at = Utc::now();
+        self.store.save_subscription(&subscription).await?;
+        Ok(subscription)
+    }
+
+    pub async fn delete_subscription(&self, tenant_id: Uuid, id: Uuid) -> Result<()> {
+        if !self.store.delete_subscription(tenant_id, id).await? {
+            return Err(Error::new(ErrorCode::NotFound, format!("Webhook subscription {} not found", id)));
+        }
+        Ok(())
+    }
+
+    /// Page of the tenant's deliveries across sources; `limit` defaults to `webhooks.page_size`
+    pub async fn deliveries(&self, tenant_id: Uuid, query: &DeliveryQuery, limit: Option<i64>) -> Result<DeliveryPage> {
+        let limit = limit
+            .unwrap_or(self.config.page_size)
+            .clamp(1, self.config.max_page_size.max(1));
+        let mut deliveries = self.store.deliveries(tenant_id, query, limit + 1).await?;
+
+        let has_more = deliveries.len() as i64 > limit;
+        deliveries.truncate(limit as usize);
+        let next_before = if has_more { deliveries.last().map(|d| d.id) } else { None };
+        Ok(DeliveryPage {
+            deliveries,
+            next_before,
+            has_more,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    /// Check a [`SIGNATURE_HEADER`] value the way receivers do, in constant time
+    fn verify(secret: &str, header: &str, body: &str) -> bool {
+        let mut timestamp = None;
+        let mut digest = None;
+        for part in header.split(',') {
+            match part.trim().split_once('=') {
+                Some(("t", value)) => timestamp = value.parse::<i64>().ok(),
+                Some(("v1", value)) => digest = hex::decode(value).ok(),
+                _ => {}
+            }
+        }
+        match (timestamp, digest) {
+            (Some(timestamp), Some(digest)) => signature(secret, timestamp, body).verify_slice(&digest).is_ok(),
+            _ => false,
+        }
+    }
+
+    struct MemorySource {
+        source: WebhookSource,
+        events: Mutex<Vec<StreamEvent>>,
+    }
+
+    impl MemorySource {
+        fn new(source: WebhookSource) -> Arc<Self> {
+            Arc::new(Self {
+                source,
+                events: Mutex::new(Vec::new()),
+            })
+        }
+
+        fn add(&self, tenant_id: Uuid, event_type: &str, at: DateTime<Utc>) {
+            let mut events = self.events.lock().unwrap();
+            let source_id = format!("{:04}", events.len() + 1);
+            events.push(StreamEvent {
+                source: self.source,
+                source_id: source_id.clone(),
+                position: at,
+                tenant_id: Some(tenant_id),
+                event_type: event_type.to_string(),
+                occurred_at: at,
+                data: json!({ "n": source_id }),
+            });
+        }
+    }
+
+    #[async_trait]
+    impl EventSource for MemorySource {
+        fn source(&self) -> WebhookSource {
+            self.source
+        }
+
+        async fn read_after(
+            &self,
+            after: Option<&HighWaterMark>,
+            settled_before: DateTime<Utc>,
+            limit: i64,
+        ) -> Result<Vec<StreamEvent>> {
+            Ok(self
+                .events
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|e| after.is_none_or(|m| (e.position, &e.source_id) > (m.position, &m.source_id)))
+                .filter(|e| e.position < settled_before)
+                .take(limit as usize)
+                .cloned()
+                .collect())
+        }
+    }
+
+    #[derive(Default)]
+    struct MemoryStore {
+        subscriptions: Mutex<Vec<Subscription>>,
+        marks: Mutex<HashMap<WebhookSource, HighWaterMark>>,
+        deliveries: Mutex<Vec<Delivery>>,
+    }
+
+    #[async_trait]
+    impl WebhookStore for MemoryStore {
+        async fn source_mark(&self, source: WebhookSource) -> Result<Option<HighWaterMark>> {
+            Ok(self.marks.lock().unwrap().get(&source).cloned())
+        }
+
+        async fn subscribers(&self, source: WebhookSource) -> Result<Vec<Subscription>> {
+            let subscriptions = self.subscriptions.lock().unwrap();
+            Ok(subscriptions.iter().filter(|s| s.active && s.sources.contains(&source)).cloned().collect())
+        }
+
+        async fn enqueue(&self, source: WebhookSource, deliveries: &[NewDelivery], mark: &HighWaterMark) -> Result<u64> {
+            let mut rows = self.deliveries.lock().unwrap();
+            let mut queued = 0;
+            for new in deliveries {
+                let lane = rows.iter().filter(|d| d.subscription_id == new.subscription_id && d.source == new.source);
+                if lane.clone().any(|d| d.source_id == new.source_id) {
+                    continue;
+                }
+                let sequence = lane.map(|d| d.sequence).max().unwrap_or(0) + 1;
+                let id = rows.len() as i64 + 1;
+                rows.push(Delivery {
+                    id,
+                    subscription_id: new.subscription_id,
+                    tenant_id: new.tenant_id,
+                    source: new.source,
+                    event_type: new.event_type.clone(),
+                    source_id: new.source_id.clone(),
+                    sequence,
+                    occurred_at: new.occurred_at,
+                    data: new.data.clone(),
+                    status: DeliveryStatus::Pending,
+                    attempts: 0,
+                    next_attempt_at: new.occurred_at,
+                    last_error: None,
+                    response_status: None,
+                    created_at: new.occurred_at,
+                    delivered_at: None,
+                });
+                queued += 1;
+            }
+            self.marks.lock().unwrap().insert(source, mark.clone());
+            Ok(queued)
+        }
+
+        async fn due(&self, now: DateTime<Utc>, limit: i64) -> Result<Vec<DueDelivery>> {
+            let rows = self.deliveries.lock().unwrap();
+            let subscriptions = self.subscriptions.lock().unwrap();
+            let mut heads: HashMap<(Uuid, WebhookSource), &Delivery> = HashMap::new();
+            for row in rows.iter().filter(|d| d.status == DeliveryStatus::Pending) {
+                let head = heads.entry((row.subscription_id, row.source)).or_insert(row);
+                if row.sequence < head.sequence {
+                    *head = row;
+                }
+            }
+            let mut due: Vec<DueDelivery> = heads
+                .into_values()
+                .filter(|d| d.next_attempt_at <= now)
+                .filter_map(|d| {
+                    let subscription = subscriptions.iter().find(|s| s.id == d.subscription_id && s.active)?;
+                    Some(DueDelivery {
+                        delivery: d.clone(),
+                        url: subscription.url.clone(),
+                        secret: subscription.secret.clone(),
+                    })
+                })
+                .collect();
+            due.sort_by_key(|d| d.delivery.id);
+            due.truncate(limit as usize);
+            Ok(due)
+        }
+
+        async fn record_attempt(&self, delivery_id: i64, outcome: &AttemptOutcome, now: DateTime<Utc>) -> Result<()> {
+            let mut rows = self.deliveries.lock().unwrap();
+            let row = rows.iter_mut().find(|d| d.id == delivery_id).unwrap();
+            row.attempts += 1;
+            match outcome {
+                AttemptOutcome::Delivered { response_status } => {
+                    row.status = DeliveryStatus::Delivered;
+                    row.response_status = Some(*response_status as i32);
+                    row.delivered_at = Some(now);
+                }
+                AttemptOutcome::Retry { error, response_status, next_attempt_at } => {
+                    row.last_error = Some(error.clone());
+                    row.response_status = response_status.map(i32::from);
+                    row.next_attempt_at = *next_attempt_at;
+                }
+                AttemptOutcome::Failed { error, response_status } => {
+                    row.status = DeliveryStatus::Failed;
+                    row.last_error = Some(error.clone());
+                    row.response_status = response_status.map(i32::from);
+                }
+            }
+            Ok(())
+        }
+
+        async fn subscriptions(&self, tenant_id: Uuid) -> Result<Vec<Subscription>> {
+            let subscriptions = self.subscriptions.lock().unwrap();
+            Ok(subscriptions.iter().filter(|s| s.tenant_id == tenant_id).cloned().collect())
+        }
+
+        async fn subscription(&self, tenant_id: Uuid, id: Uuid) -> Result<Option<Subscription>> {
+            let subscriptions = self.subscriptions.lock().unwrap();
+            Ok(subscriptions.iter().find(|s| s.tenant_id == tenant_id && s.id == id).cloned())
+        }
+
+        async fn save_subscription(&self, subscription: &Subscription) -> Result<()> {
+            let mut subscriptions = self.subscriptions.lock().unwrap();
+            subscriptions.retain(|s| s.id != subscription.id);
+            subscriptions.push(subscription.clone());
+            Ok(())
+        }
+
+        async fn delete_subscription(&self, tenant_id: Uuid, id: Uuid) -> Result<bool> {
+            let mut subscriptions = self.subscriptions.lock().unwrap();
+            let before = subscriptions.len();
+            subscriptions.retain(|s| !(s.tenant_id == tenant_id && s.id == id));
+            Ok(subscriptions.len() < before)
+        }
+
+        async fn deliveries(&self, tenant_id: Uuid, query: &DeliveryQuery, limit: i64) -> Result<Vec<Delivery>> {
+            let rows = self.deliveries.lock().unwrap();
+            Ok(rows
+                .iter()
+                .rev()
+                .filter(|d| d.tenant_id == tenant_id)
+                .filter(|d| query.source.is_none_or(|s| d.source == s))
+                .filter(|d| query.event_type.as_ref().is_none_or(|t| &d.event_type == t))
+                .filter(|d| query.status.is_none_or(|s| d.status == s))
+                .filter(|d| query.subscription_id.is_none_or(|s| d.subscription_id == s))
+                .filter(|d| query.before_id.is_none_or(|b| d.id < b))
+                .take(limit as usize)
+                .cloned()
+                .collect())
+        }
+
+        async fn count_before(&self, _tenant_id: Uuid, _cutoff: DateTime<Utc>) -> Result<u64> {
+            Ok(0)
+        }
+
+        async fn delete_before(&self, _tenant_id: Uuid, _cutoff: DateTime<Utc>, _limit: i64) -> Result<u64> {
+            Ok(0)
+        }
+    }
+
+    /// Records what each URL received; posts of a failing source answer 503
+    #[derive(Default)]
+    struct RecordingTransport {
+        posts: Mutex<Vec<(String, String, Envelope)>>,
+        failing: Mutex<Option<WebhookSource>>,
+    }
+
+    impl RecordingTransport {
+        fn received(&self, url: &str, source: WebhookSource) -> Vec<i64> {
+            let posts = self.posts.lock().unwrap();
+            posts
+                .iter()
+                .filter(|(u, _, e)| u == url && e.source == source)
+                .map(|(_, _, e)| e.sequence)
+                .collect()
+        }
+    }
+
+    #[async_trait]
+    impl WebhookTransport for RecordingTransport {
+        async fn post(&self, url: &str, headers: &[(&'static str, String)], body: String) -> Result<u16> {
+            let envelope: Envelope = serde_json::from_str(&body).unwrap();
+            if *self.failing.lock().unwrap() == Some(envelope.source) {
+                return Ok(503);
+            }
+            let signature = headers.iter().find(|(name, _)| *name == SIGNATURE_HEADER).unwrap().1.clone();
+            self.posts.lock().unwrap().push((url.to_string(), signature, envelope));
+            Ok(200)
+        }
+    }
+
+    fn config() -> WebhooksConfig {
+        WebhooksConfig {
+            max_attempts: 3,
+            retry_backoff_secs: 60,
+            ..WebhooksConfig::default()
+        }
+    }
+
+    struct Fixture {
+        store: Arc<MemoryStore>,
+        transport: Arc<RecordingTransport>,
+        inventory: Arc<MemorySource>,
+        auth: Arc<MemorySource>,
+        service: WebhookService,
+        tenant_id: Uuid,
+    }
+
+    fn fixture() -> Fixture {
+        let store = Arc::new(MemoryStore::default());
+        let transport = Arc::new(RecordingTransport::default());
+        let inventory = MemorySource::new(WebhookSource::Inventory);
+        let auth = MemorySource::new(WebhookSource::Auth);
+        let service = WebhookService::new(
+            store.clone(),
+            vec![inventory.clone(), auth.clone()],
+            transport.clone(),
+            config(),
+        );
+        Fixture {
+            store,
+            transport,
+            inventory,
+            auth,
+            service,
+            tenant_id: Uuid::new_v4(),
+        }
+    }
+
+    fn request(url: &str, sources: &[&str], event_types: &[&str]) -> SubscriptionRequest {
+        SubscriptionRequest {
+            name: "SIEM".to_string(),
+            url: url.to_string(),
+            sources: sources.iter().map(|s| s.to_string()).collect(),
+            event_types: event_types.iter().map(|t| t.to_string()).collect(),
+            active: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_inventory_only_subscription_receives_no_auth_events() {
+        let f = fixture();
+        let ops = f
+            .service
+            .create_subscription(f.tenant_id, None, &request("https://ops.example.com/hook", &["inventory"], &[]))
+            .await
+            .unwrap();
+        let siem = f
+            .service
+            .create_subscription(
+                f.tenant_id,
+                None,
+                &request("https://siem.example.com/hook", &["inventory", "auth"], &["auth.account_locked"]),
+            )
+            .await
+            .unwrap();
+        // Another tenant's subscription to everything sees none of these events
+        f.service
+            .create_subscription(
+                Uuid::new_v4(),
+                None,
+                &request("https://other.example.com/hook", &["inventory", "auth"], &[]),
+            )
+            .await
+            .unwrap();
+
+        let t0 = Utc::now() - Duration::minutes(10);
+        f.inventory.add(f.tenant_id, "stockout", t0);
+        f.auth.add(f.tenant_id, "account_locked", t0);
+        f.auth.add(f.tenant_id, "role_assigned", t0 + Duration::seconds(1));
+        f.inventory.add(f.tenant_id, "below_reorder", t0 + Duration::seconds(2));
+
+        let run = f.service.run_once(Utc::now()).await.unwrap();
+        assert_eq!(run.delivered, 5);
+
+        assert_eq!(f.transport.received(&ops.url, WebhookSource::Inventory), vec![1, 2]);
+        assert!(f.transport.received(&ops.url, WebhookSource::Auth).is_empty());
+        // Only the listed auth type, every inventory type
+        assert_eq!(f.transport.received(&siem.url, WebhookSource::Auth), vec![1]);
+        assert_eq!(f.transport.received(&siem.url, WebhookSource::Inventory), vec![1, 2]);
+        assert!(f.transport.received("https://other.example.com/hook", WebhookSource::Inventory).is_empty());
+
+        let posts = f.transport.posts.lock().unwrap();
+        let (_, signature, envelope) = posts.iter().find(|(u, _, e)| u == &siem.url && e.source == WebhookSource::Auth).unwrap();
+        assert_eq!(envelope.event_type, "account_locked");
+        assert_eq!(envelope.tenant_id, f.tenant_id);
+        let body = serde_json::to_string(envelope).unwrap();
+        assert!(verify(&siem.secret, signature, &body));
+        assert!(!verify(&ops.secret, signature, &body));
+    }
+
+    #[tokio::test]
+    async fn test_ordering_per_source_survives_a_failing_lane() {
+        let f = fixture();
+        let subscription = f
+            .service
+            .create_subscription(f.tenant_id, None, &request("https://hooks.example.com/erp", &["inventory", "auth"], &[]))
+            .await
+            .unwrap();
+
+        let t0 = Utc::now() - Duration::minutes(10);
+        for i in 0..3 {
+            f.auth.add(f.tenant_id, "role_assigned", t0 + Duration::seconds(i));
+            f.inventory.add(f.tenant_id, "stockout", t0 + Duration::seconds(i));
+        }
+        *f.transport.failing.lock().unwrap() = Some(WebhookSource::Auth);
+
+        let now = Utc::now();
+        let run = f.service.run_once(now).await.unwrap();
+        // The auth lane's head was tried once; inventory went through in order
+        assert_eq!(run, DeliveryRun { delivered: 3, retried: 1, failed: 0 });
+        assert_eq!(f.transport.received(&subscription.url, WebhookSource::Inventory), vec![1, 2, 3]);
+        assert!(f.transport.received(&subscription.url, WebhookSource::Auth).is_empty());
+
+        // New inventory events don't wait for the auth lane either
+        f.inventory.add(f.tenant_id, "below_reorder", now - Duration::seconds(30));
+        f.service.run_once(now).await.unwrap();
+        assert_eq!(f.transport.received(&subscription.url, WebhookSource::Inventory), vec![1, 2, 3, 4]);
+
+        // Once the endpoint recovers and the backoff passed, auth catches up in order
+        *f.transport.failing.lock().unwrap() = None;
+        let run = f.service.run_once(now + Duration::seconds(61)).await.unwrap();
+        assert_eq!(run.delivered, 3);
+        assert_eq!(f.transport.received(&subscription.url, WebhookSource::Auth), vec![1, 2, 3]);
+
+        // Replaying the sources from scratch queues nothing twice
+        f.store.marks.lock().unwrap().clear();
+        assert_eq!(f.service.fan_out_all(now + Duration::seconds(61)).await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_lane_moves_on_after_max_attempts() {
+        let f = fixture();
+        let subscription = f
+            .service
+            .create_subscription(f.tenant_id, None, &request("https://hooks.example.com/erp", &["auth"], &[]))
+            .await
+            .unwrap();
+        let t0 = Utc::now() - Duration::minutes(10);
+        f.auth.add(f.tenant_id, "user_created", t0);
+        *f.transport.failing.lock().unwrap() = Some(WebhookSource::Auth);
+
+        let mut now = Utc::now();
+        for _ in 0..3 {
+            f.service.run_once(now).await.unwrap();
+            now += Duration::hours(1);
+        }
+        f.auth.add(f.tenant_id, "user_created", now - Duration::minutes(1));
+        *f.transport.failing.lock().unwrap() = None;
+        f.service.run_once(now).await.unwrap();
+
+        assert_eq!(f.transport.received(&subscription.url, WebhookSource::Auth), vec![2]);
+        let page = f
+            .service
+            .deliveries(
+                f.tenant_id,
+                &DeliveryQuery {
+                    status: Some(DeliveryStatus::Failed),
+                    ..DeliveryQuery::default()
+                },
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(page.deliveries.len(), 1);
+        assert_eq!(page.deliveries[0].attempts, 3);
+        assert_eq!(page.deliveries[0].response_status, Some(503));
+    }
+
+    #[test]
+    fn test_subscription_requests_are_checked_against_the_catalog() {
+        assert!(request("https://siem.example.com", &["auth"], &["auth.account_locked"]).validate().is_ok());
+        assert!(request("http://siem.example.com", &["auth"], &[]).validate().is_err());
+        assert!(request("https://siem.example.com", &[], &[]).validate().is_err());
+        assert!(request("https://siem.example.com", &["billing"], &[]).validate().is_err());
+        assert!(request("https://siem.example.com", &["auth"], &["auth.password_changed"]).validate().is_err());
+        // A type of a source the subscription doesn't take
+        assert!(request("https://siem.example.com", &["auth"], &["inventory.stockout"]).validate().is_err());
+    }
+}
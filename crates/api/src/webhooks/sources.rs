@@ -0,0 +1,531 @@
+//! Event sources of the dispatcher and the catalog of event types
+//!
+//! Each source reads its own store in mark order and publishes only the event
+//! types listed in [`EVENT_TYPES`]:
+//!
+//! - `customer`: `customer_events`, by `recorded_at`; `data` is the event's
+//!   own payload
+//! - `inventory`: `public.inventory_events`, by `occurred_at`, written by
+//!   triggers when stock runs out or falls to the reorder point and when a
+//!   transfer completes
+//! - `auth`: `audit_events`, by `created_at`, limited to user creation, role
+//!   changes and lockouts after failed logins
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use erp_core::Result;
+use serde_json::{json, Value};
+use sqlx::{PgPool, Row};
+use uuid::Uuid;
+
+use super::{EventSource, StreamEvent, WebhookSource};
+use crate::activity::HighWaterMark;
+
+/// One event type a source publishes, with the JSON Schema of its `data`
+pub struct EventTypeDoc {
+    pub source: WebhookSource,
+    pub event_type: &'static str,
+    pub description: &'static str,
+    pub data_schema: fn() -> Value,
+    /// A `data` value as delivered
+    pub example: fn() -> Value,
+}
+
+impl EventTypeDoc {
+    /// `inventory.stockout`, as in subscription filters and schema file names
+    pub fn qualified_name(&self) -> String {
+        format!("{}.{}", self.source.as_str(), self.event_type)
+    }
+}
+
+fn uuid_field() -> Value {
+    json!({ "type": "string", "format": "uuid" })
+}
+
+fn time_field() -> Value {
+    json!({ "type": "string", "format": "date-time" })
+}
+
+fn optional(type_name: &str) -> Value {
+    json!({ "type": [type_name, "null"] })
+}
+
+/// Object with `required` fields; further fields may be added without notice
+fn object(required: &[&str], properties: Value) -> Value {
+    json!({ "type": "object", "required": required, "properties": properties })
+}
+
+fn audit_data() -> Value {
+    object(
+        &["actor_id", "resource_type", "resource_id", "description", "details"],
+        json!({
+            "actor_id": optional("string"),
+            "resource_type": optional("string"),
+            "resource_id": optional("string"),
+            "description": { "type": "string" },
+            "details": { "type": "object" }
+        }),
+    )
+}
+
+fn audit_example(description: &str, details: Value) -> Value {
+    json!({
+        "actor_id": "7f1c2d8e-3b4a-4c5d-9e6f-0a1b2c3d4e5f",
+        "resource_type": "user",
+        "resource_id": "0c9b8a7d-6e5f-4a3b-8c2d-1e0f9a8b7c6d",
+        "description": description,
+        "details": details
+    })
+}
+
+pub const EVENT_TYPES: &[EventTypeDoc] = &[
+    EventTypeDoc {
+        source: WebhookSource::Customer,
+        event_type: "customer_created",
+        description: "A customer was created",
+        data_schema: || {
+            object(
+                &["customer_id", "customer_number", "legal_name", "customer_type", "created_by", "created_at"],
+                json!({
+                    "customer_id": uuid_field(),
+                    "tenant_id": uuid_field(),
+                    "customer_number": { "type": "string" },
+                    "legal_name": { "type": "string" },
+                    "customer_type": { "type": "string" },
+                    "created_by": uuid_field(),
+                    "created_at": time_field()
+                }),
+            )
+        },
+        example: || {
+            json!({
+                "customer_id": "3f2a1b0c-9d8e-4f7a-8b6c-5d4e3f2a1b0c",
+                "tenant_id": "9a8b7c6d-5e4f-4a3b-9c2d-1e0f9a8b7c6d",
+                "customer_number": "C-000123",
+                "legal_name": "Acme GmbH",
+                "customer_type": "B2b",
+                "created_by": "7f1c2d8e-3b4a-4c5d-9e6f-0a1b2c3d4e5f",
+                "created_at": "2026-10-17T08:30:00Z"
+            })
+        },
+    },
+    EventTypeDoc {
+        source: WebhookSource::Customer,
+        event_type: "customer_information_updated",
+        description: "A customer's legal name or type changed",
+        data_schema: || {
+            object(
+                &["customer_id", "updated_by", "updated_at"],
+                json!({
+                    "customer_id": uuid_field(),
+                    "previous_legal_name": optional("string"),
+                    "new_legal_name": optional("string"),
+                    "previous_customer_type": optional("string"),
+                    "new_customer_type": optional("string"),
+                    "updated_by": uuid_field(),
+                    "updated_at": time_field()
+                }),
+            )
+        },
+        example: || {
+            json!({
+                "customer_id": "3f2a1b0c-9d8e-4f7a-8b6c-5d4e3f2a1b0c",
+                "previous_legal_name": "Acme GmbH",
+                "new_legal_name": "Acme Holding GmbH",
+                "previous_customer_type": null,
+                "new_customer_type": null,
+                "updated_by": "7f1c2d8e-3b4a-4c5d-9e6f-0a1b2c3d4e5f",
+                "updated_at": "2026-10-17T08:30:00Z"
+            })
+        },
+    },
+    EventTypeDoc {
+        source: WebhookSource::Customer,
+        event_type: "lifecycle_stage_changed",
+        description: "A customer moved to another lifecycle stage",
+        data_schema: || {
+            object(
+                &["customer_id", "previous_stage", "new_stage", "changed_by", "changed_at"],
+                json!({
+                    "customer_id": uuid_field(),
+                    "previous_stage": { "type": "string" },
+                    "new_stage": { "type": "string" },
+                    "reason": optional("string"),
+                    "changed_by": uuid_field(),
+                    "changed_at": time_field()
+                }),
+            )
+        },
+        example: || {
+            json!({
+                "customer_id": "3f2a1b0c-9d8e-4f7a-8b6c-5d4e3f2a1b0c",
+                "previous_stage": "Prospect",
+                "new_stage": "ActiveCustomer",
+                "reason": "First order",
+                "changed_by": "7f1c2d8e-3b4a-4c5d-9e6f-0a1b2c3d4e5f",
+                "changed_at": "2026-10-17T08:30:00Z"
+            })
+        },
+    },
+    EventTypeDoc {
+        source: WebhookSource::Customer,
+        event_type: "credit_status_changed",
+        description: "A customer's credit status or limit changed",
+        data_schema: || {
+            object(
+                &["customer_id", "previous_status", "new_status", "reason", "approved_by", "changed_at"],
+                json!({
+                    "customer_id": uuid_field(),
+                    "previous_status": { "type": "string" },
+                    "new_status": { "type": "string" },
+                    "previous_limit": { "type": ["string", "number", "null"] },
+                    "new_limit": { "type": ["string", "number", "null"] },
+                    "reason": { "type": "string" },
+                    "approved_by": uuid_field(),
+                    "changed_at": time_field()
+                }),
+            )
+        },
+        example: || {
+            json!({
+                "customer_id": "3f2a1b0c-9d8e-4f7a-8b6c-5d4e3f2a1b0c",
+                "previous_status": "Good",
+                "new_status": "OnHold",
+                "previous_limit": "5000.00",
+                "new_limit": "0.00",
+                "reason": "Overdue invoices",
+                "approved_by": "7f1c2d8e-3b4a-4c5d-9e6f-0a1b2c3d4e5f",
+                "changed_at": "2026-10-17T08:30:00Z"
+            })
+        },
+    },
+    EventTypeDoc {
+        source: WebhookSource::Customer,
+        event_type: "customer_soft_deleted",
+        description: "A customer was deleted; it can be restored until purged",
+        data_schema: || {
+            object(
+                &["customer_id", "reason", "deleted_by", "deleted_at"],
+                json!({
+                    "customer_id": uuid_field(),
+                    "reason": { "type": "string" },
+                    "deleted_by": uuid_field(),
+                    "deleted_at": time_field()
+                }),
+            )
+        },
+        example: || {
+            json!({
+                "customer_id": "3f2a1b0c-9d8e-4f7a-8b6c-5d4e3f2a1b0c",
+                "reason": "Duplicate",
+                "deleted_by": "7f1c2d8e-3b4a-4c5d-9e6f-0a1b2c3d4e5f",
+                "deleted_at": "2026-10-17T08:30:00Z"
+            })
+        },
+    },
+    EventTypeDoc {
+        source: WebhookSource::Customer,
+        event_type: "customer_restored",
+        description: "A deleted customer was restored",
+        data_schema: || {
+            object(
+                &["customer_id", "reason", "restored_by", "restored_at"],
+                json!({
+                    "customer_id": uuid_field(),
+                    "reason": { "type": "string" },
+                    "restored_by": uuid_field(),
+                    "restored_at": time_field()
+                }),
+            )
+        },
+        example: || {
+            json!({
+                "customer_id": "3f2a1b0c-9d8e-4f7a-8b6c-5d4e3f2a1b0c",
+                "reason": "Deleted by mistake",
+                "restored_by": "7f1c2d8e-3b4a-4c5d-9e6f-0a1b2c3d4e5f",
+                "restored_at": "2026-10-17T08:30:00Z"
+            })
+        },
+    },
+    EventTypeDoc {
+        source: WebhookSource::Inventory,
+        event_type: "stockout",
+        description: "Available stock of a product at a location reached zero",
+        data_schema: || {
+            object(
+                &["product_id", "location_id", "quantity_available", "previous_quantity"],
+                json!({
+                    "product_id": uuid_field(),
+                    "location_id": uuid_field(),
+                    "sku": optional("string"),
+                    "quantity_available": { "type": "integer" },
+                    "previous_quantity": { "type": "integer" }
+                }),
+            )
+        },
+        example: || {
+            json!({
+                "product_id": "5b4a3c2d-1e0f-4a9b-8c7d-6e5f4a3b2c1d",
+                "location_id": "1d2c3b4a-5f6e-4d7c-8b9a-0f1e2d3c4b5a",
+                "sku": "WID-001",
+                "quantity_available": 0,
+                "previous_quantity": 4
+            })
+        },
+    },
+    EventTypeDoc {
+        source: WebhookSource::Inventory,
+        event_type: "below_reorder",
+        description: "Available stock of a product at a location fell to its reorder point",
+        data_schema: || {
+            object(
+                &["product_id", "location_id", "quantity_available", "reorder_point"],
+                json!({
+                    "product_id": uuid_field(),
+                    "location_id": uuid_field(),
+                    "sku": optional("string"),
+                    "quantity_available": { "type": "integer" },
+                    "reorder_point": { "type": "integer" }
+                }),
+            )
+        },
+        example: || {
+            json!({
+                "product_id": "5b4a3c2d-1e0f-4a9b-8c7d-6e5f4a3b2c1d",
+                "location_id": "1d2c3b4a-5f6e-4d7c-8b9a-0f1e2d3c4b5a",
+                "sku": "WID-001",
+                "quantity_available": 12,
+                "reorder_point": 15
+            })
+        },
+    },
+    EventTypeDoc {
+        source: WebhookSource::Inventory,
+        event_type: "transfer_completed",
+        description: "A stock transfer was received at its destination",
+        data_schema: || {
+            object(
+                &["product_id", "location_id", "transfer_id", "from_location_id", "to_location_id", "quantity"],
+                json!({
+                    "product_id": uuid_field(),
+                    "location_id": uuid_field(),
+                    "transfer_id": uuid_field(),
+                    "sku": optional("string"),
+                    "from_location_id": uuid_field(),
+                    "to_location_id": uuid_field(),
+                    "quantity": { "type": "integer" },
+                    "quantity_received": optional("integer")
+                }),
+            )
+        },
+        example: || {
+            json!({
+                "product_id": "5b4a3c2d-1e0f-4a9b-8c7d-6e5f4a3b2c1d",
+                "location_id": "1d2c3b4a-5f6e-4d7c-8b9a-0f1e2d3c4b5a",
+                "transfer_id": "8e7d6c5b-4a3f-4e2d-9c1b-0a9f8e7d6c5b",
+                "sku": "WID-001",
+                "from_location_id": "2e3d4c5b-6a7f-4e8d-9c0b-1a2f3e4d5c6b",
+                "to_location_id": "1d2c3b4a-5f6e-4d7c-8b9a-0f1e2d3c4b5a",
+                "quantity": 20,
+                "quantity_received": 20
+            })
+        },
+    },
+    EventTypeDoc {
+        source: WebhookSource::Auth,
+        event_type: "user_created",
+        description: "A user was created in the tenant",
+        data_schema: audit_data,
+        example: || audit_example("User created", json!({ "email": "jane.doe@example.com" })),
+    },
+    EventTypeDoc {
+        source: WebhookSource::Auth,
+        event_type: "role_assigned",
+        description: "A role was assigned to a user",
+        data_schema: audit_data,
+        example: || audit_example("Role assigned", json!({ "role": "inventory_manager" })),
+    },
+    EventTypeDoc {
+        source: WebhookSource::Auth,
+        event_type: "role_revoked",
+        description: "A role was taken from a user",
+        data_schema: audit_data,
+        example: || audit_example("Role revoked", json!({ "role": "inventory_manager" })),
+    },
+    EventTypeDoc {
+        source: WebhookSource::Auth,
+        event_type: "account_locked",
+        description: "A user was locked out after repeated failed logins",
+        data_schema: audit_data,
+        example: || {
+            audit_example(
+                "User account locked after failed logins",
+                json!({ "previous_state": "active", "state": "locked", "locked_until": "2026-10-17T08:45:00Z" }),
+            )
+        },
+    },
+];
+
+/// Audit event types published by the `auth` source, with their webhook names
+const AUTH_EVENT_TYPES: [(&str, &str); 4] = [
+    ("UserCreated", "user_created"),
+    ("RoleAssigned", "role_assigned"),
+    ("RoleRevoked", "role_revoked"),
+    ("AccountLocked", "account_locked"),
+];
+
+/// Event types of one source
+pub fn event_types(source: WebhookSource) -> impl Iterator<Item = &'static EventTypeDoc> {
+    EVENT_TYPES.iter().filter(move |doc| doc.source == source)
+}
+
+pub fn event_type(source: WebhookSource, event_type: &str) -> Option<&'static EventTypeDoc> {
+    event_types(source).find(|doc| doc.event_type == event_type)
+}
+
+fn type_names(source: WebhookSource) -> Vec<String> {
+    event_types(source).map(|doc| doc.event_type.to_string()).collect()
+}
+
+fn events(source: WebhookSource, rows: Vec<sqlx::postgres::PgRow>) -> Vec<StreamEvent> {
+    let uuid = |value: Option<String>| value.and_then(|v| v.parse::<Uuid>().ok());
+    rows.iter()
+        .map(|row| StreamEvent {
+            source,
+            source_id: row.get("source_id"),
+            position: row.get("position"),
+            tenant_id: uuid(row.get("tenant_id")),
+            event_type: row.get("event_type"),
+            occurred_at: row.get("occurred_at"),
+            data: row.get("data"),
+        })
+        .collect()
+}
+
+pub struct CustomerEventSource {
+    pool: PgPool,
+}
+
+impl CustomerEventSource {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl EventSource for CustomerEventSource {
+    fn source(&self) -> WebhookSource {
+        WebhookSource::Customer
+    }
+
+    async fn read_after(
+        &self,
+        after: Option<&HighWaterMark>,
+        settled_before: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<StreamEvent>> {
+        // Stored as {"event_type": ..., "data": {...}}
+        let rows = sqlx::query(
+            "SELECT event_id::TEXT AS source_id, recorded_at AS position, tenant_id::TEXT AS tenant_id, \
+                 event_type, occurred_at, COALESCE(event_data->'data', event_data) AS data \
+             FROM customer_events \
+             WHERE ($1::TIMESTAMPTZ IS NULL OR (recorded_at, event_id::TEXT) > ($1, $2)) AND recorded_at < $3 \
+               AND event_type = ANY($5) \
+             ORDER BY recorded_at, event_id::TEXT LIMIT $4",
+        )
+        .bind(after.map(|mark| mark.position))
+        .bind(after.map(|mark| mark.source_id.clone()).unwrap_or_default())
+        .bind(settled_before)
+        .bind(limit)
+        .bind(type_names(WebhookSource::Customer))
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(events(WebhookSource::Customer, rows))
+    }
+}
+
+pub struct InventoryEventSource {
+    pool: PgPool,
+}
+
+impl InventoryEventSource {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl EventSource for InventoryEventSource {
+    fn source(&self) -> WebhookSource {
+        WebhookSource::Inventory
+    }
+
+    async fn read_after(
+        &self,
+        after: Option<&HighWaterMark>,
+        settled_before: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<StreamEvent>> {
+        let rows = sqlx::query(
+            "SELECT id::TEXT AS source_id, occurred_at AS position, tenant_id::TEXT AS tenant_id, event_type, \
+                 occurred_at, data || jsonb_build_object('product_id', product_id, 'location_id', location_id) AS data \
+             FROM public.inventory_events \
+             WHERE ($1::TIMESTAMPTZ IS NULL OR (occurred_at, id::TEXT) > ($1, $2)) AND occurred_at < $3 \
+             ORDER BY occurred_at, id::TEXT LIMIT $4",
+        )
+        .bind(after.map(|mark| mark.position))
+        .bind(after.map(|mark| mark.source_id.clone()).unwrap_or_default())
+        .bind(settled_before)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(events(WebhookSource::Inventory, rows))
+    }
+}
+
+pub struct AuthEventSource {
+    pool: PgPool,
+}
+
+impl AuthEventSource {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl EventSource for AuthEventSource {
+    fn source(&self) -> WebhookSource {
+        WebhookSource::Auth
+    }
+
+    async fn read_after(
+        &self,
+        after: Option<&HighWaterMark>,
+        settled_before: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<StreamEvent>> {
+        let (audit_types, names): (Vec<&str>, Vec<&str>) = AUTH_EVENT_TYPES.iter().copied().unzip();
+        let rows = sqlx::query(
+            "SELECT id AS source_id, created_at AS position, tenant_id, \
+                 ($6::TEXT[])[array_position($5::TEXT[], event_type::TEXT)] AS event_type, \
+                 timestamp AS occurred_at, \
+                 jsonb_build_object('actor_id', actor_id, 'resource_type', resource_type, \
+                     'resource_id', resource_id, 'description', description, \
+                     'details', COALESCE(metadata, '{}'::JSONB)) AS data \
+             FROM audit_events \
+             WHERE ($1::TIMESTAMPTZ IS NULL OR (created_at, id) > ($1, $2)) AND created_at < $3 \
+               AND event_type = ANY($5) \
+             ORDER BY created_at, id LIMIT $4",
+        )
+        .bind(after.map(|mark| mark.position))
+        .bind(after.map(|mark| mark.source_id.clone()).unwrap_or_default())
+        .bind(settled_before)
+        .bind(limit)
+        .bind(audit_types)
+        .bind(names)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(events(WebhookSource::Auth, rows))
+    }
+}
@@ -0,0 +1,343 @@
+//! Postgres store of subscriptions and deliveries, and the outbound transport
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use erp_core::outbound::OutboundClient;
+use erp_core::{Error, ErrorCode, Result};
+use sqlx::postgres::PgRow;
+use sqlx::{PgPool, Row};
+use uuid::Uuid;
+
+use super::{
+    AttemptOutcome, Delivery, DeliveryQuery, DeliveryStatus, DueDelivery, NewDelivery, Subscription, WebhookSource,
+    WebhookStore, WebhookTransport,
+};
+use crate::activity::HighWaterMark;
+
+const SUBSCRIPTION_COLUMNS: &str =
+    "id, tenant_id, name, url, secret, sources, event_types, active, created_by, created_at, updated_at";
+
+/// Finished deliveries of tenant `$1` created before `$2`; the last of each lane stays,
+/// it carries the sequence the lane's next delivery is numbered after
+const EXPIRED_DELIVERIES: &str = "d.tenant_id = $1 AND d.created_at < $2 AND d.status <> 'pending' \
+     AND EXISTS (SELECT 1 FROM public.webhook_deliveries n \
+                 WHERE n.subscription_id = d.subscription_id AND n.source = d.source AND n.sequence > d.sequence)";
+
+const DELIVERY_COLUMNS: &str = "d.id, d.subscription_id, d.tenant_id, d.source, d.event_type, d.source_id, \
+     d.sequence, d.occurred_at, d.data, d.status, d.attempts, d.next_attempt_at, d.last_error, d.response_status, \
+     d.created_at, d.delivered_at";
+
+fn corrupt(what: &str, value: &str) -> Error {
+    Error::new(ErrorCode::DatabaseError, format!("Unknown webhook {} '{}'", what, value))
+}
+
+fn map_subscription(row: &PgRow) -> Result<Subscription> {
+    let sources: Vec<String> = row.get("sources");
+    Ok(Subscription {
+        id: row.get("id"),
+        tenant_id: row.get("tenant_id"),
+        name: row.get("name"),
+        url: row.get("url"),
+        secret: row.get("secret"),
+        sources: sources
+            .iter()
+            .map(|s| WebhookSource::parse(s).ok_or_else(|| corrupt("source", s)))
+            .collect::<Result<_>>()?,
+        event_types: row.get("event_types"),
+        active: row.get("active"),
+        created_by: row.get("created_by"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+    })
+}
+
+fn map_delivery(row: &PgRow) -> Result<Delivery> {
+    let source: String = row.get("source");
+    let status: String = row.get("status");
+    Ok(Delivery {
+        id: row.get("id"),
+        subscription_id: row.get("subscription_id"),
+        tenant_id: row.get("tenant_id"),
+        source: WebhookSource::parse(&source).ok_or_else(|| corrupt("source", &source))?,
+        event_type: row.get("event_type"),
+        source_id: row.get("source_id"),
+        sequence: row.get("sequence"),
+        occurred_at: row.get("occurred_at"),
+        data: row.get("data"),
+        status: DeliveryStatus::parse(&status).ok_or_else(|| corrupt("delivery status", &status))?,
+        attempts: row.get("attempts"),
+        next_attempt_at: row.get("next_attempt_at"),
+        last_error: row.get("last_error"),
+        response_status: row.get("response_status"),
+        created_at: row.get("created_at"),
+        delivered_at: row.get("delivered_at"),
+    })
+}
+
+pub struct PostgresWebhookStore {
+    pool: PgPool,
+}
+
+impl PostgresWebhookStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl WebhookStore for PostgresWebhookStore {
+    async fn source_mark(&self, source: WebhookSource) -> Result<Option<HighWaterMark>> {
+        let row = sqlx::query("SELECT position, source_id FROM public.webhook_source_marks WHERE source = $1")
+            .bind(source.as_str())
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(row.map(|row| HighWaterMark {
+            position: row.get("position"),
+            source_id: row.get("source_id"),
+        }))
+    }
+
+    async fn subscribers(&self, source: WebhookSource) -> Result<Vec<Subscription>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM public.webhook_subscriptions WHERE active AND $1 = ANY(sources)",
+            SUBSCRIPTION_COLUMNS
+        ))
+        .bind(source.as_str())
+        .fetch_all(&self.pool)
+        .await?;
+        rows.iter().map(map_subscription).collect()
+    }
+
+    async fn enqueue(&self, source: WebhookSource, deliveries: &[NewDelivery], mark: &HighWaterMark) -> Result<u64> {
+        let mut tx = self.pool.begin().await?;
+        let mut queued = 0;
+        for delivery in deliveries {
+            // One numbering per lane: hold the lane's advisory lock while reading its last sequence
+            sqlx::query("SELECT pg_advisory_xact_lock(hashtext($1::TEXT || ':' || $2))")
+                .bind(delivery.subscription_id)
+                .bind(source.as_str())
+                .execute(&mut *tx)
+                .await?;
+            let result = sqlx::query(
+                "INSERT INTO public.webhook_deliveries \
+                 (subscription_id, tenant_id, source, event_type, source_id, sequence, occurred_at, data, next_attempt_at) \
+                 SELECT $1, $2, $3, $4, $5, COALESCE(MAX(sequence), 0) + 1, $6, $7, NOW() \
+                 FROM public.webhook_deliveries WHERE subscription_id = $1 AND source = $3 \
+                 ON CONFLICT (subscription_id, source, source_id) DO NOTHING",
+            )
+            .bind(delivery.subscription_id)
+            .bind(delivery.tenant_id)
+            .bind(source.as_str())
+            .bind(&delivery.event_type)
+            .bind(&delivery.source_id)
+            .bind(delivery.occurred_at)
+            .bind(&delivery.data)
+            .execute(&mut *tx)
+            .await?;
+            queued += result.rows_affected();
+        }
+
+        sqlx::query(
+            "INSERT INTO public.webhook_source_marks (source, position, source_id, updated_at) \
+             VALUES ($1, $2, $3, NOW()) \
+             ON CONFLICT (source) DO UPDATE SET \
+                 position = EXCLUDED.position, source_id = EXCLUDED.source_id, updated_at = NOW()",
+        )
+        .bind(source.as_str())
+        .bind(mark.position)
+        .bind(&mark.source_id)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(queued)
+    }
+
+    async fn due(&self, now: DateTime<Utc>, limit: i64) -> Result<Vec<DueDelivery>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {}, s.url, s.secret FROM ( \
+                 SELECT DISTINCT ON (subscription_id, source) * FROM public.webhook_deliveries \
+                 WHERE status = 'pending' ORDER BY subscription_id, source, sequence) d \
+             JOIN public.webhook_subscriptions s ON s.id = d.subscription_id \
+             WHERE s.active AND d.next_attempt_at <= $1 \
+             ORDER BY d.next_attempt_at, d.id LIMIT $2",
+            DELIVERY_COLUMNS
+        ))
+        .bind(now)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        rows.iter()
+            .map(|row| {
+                Ok(DueDelivery {
+                    delivery: map_delivery(row)?,
+                    url: row.get("url"),
+                    secret: row.get("secret"),
+                })
+            })
+            .collect()
+    }
+
+    async fn record_attempt(&self, delivery_id: i64, outcome: &AttemptOutcome, now: DateTime<Utc>) -> Result<()> {
+        let (status, error, response_status, next_attempt_at) = match outcome {
+            AttemptOutcome::Delivered { response_status } => {
+                (DeliveryStatus::Delivered, None, Some(*response_status), None)
+            }
+            AttemptOutcome::Retry {
+                error,
+                response_status,
+                next_attempt_at,
+            } => (DeliveryStatus::Pending, Some(error.as_str()), *response_status, Some(*next_attempt_at)),
+            AttemptOutcome::Failed { error, response_status } => {
+                (DeliveryStatus::Failed, Some(error.as_str()), *response_status, None)
+            }
+        };
+        sqlx::query(
+            "UPDATE public.webhook_deliveries SET \
+                 status = $2, attempts = attempts + 1, last_error = $3, response_status = $4, \
+                 next_attempt_at = COALESCE($5, next_attempt_at), \
+                 delivered_at = CASE WHEN $2 = 'delivered' THEN $6 ELSE delivered_at END \
+             WHERE id = $1",
+        )
+        .bind(delivery_id)
+        .bind(status.as_str())
+        .bind(error)
+        .bind(response_status.map(i32::from))
+        .bind(next_attempt_at)
+        .bind(now)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn subscriptions(&self, tenant_id: Uuid) -> Result<Vec<Subscription>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM public.webhook_subscriptions WHERE tenant_id = $1 ORDER BY created_at, id",
+            SUBSCRIPTION_COLUMNS
+        ))
+        .bind(tenant_id)
+        .fetch_all(&self.pool)
+        .await?;
+        rows.iter().map(map_subscription).collect()
+    }
+
+    async fn subscription(&self, tenant_id: Uuid, id: Uuid) -> Result<Option<Subscription>> {
+        let row = sqlx::query(&format!(
+            "SELECT {} FROM public.webhook_subscriptions WHERE tenant_id = $1 AND id = $2",
+            SUBSCRIPTION_COLUMNS
+        ))
+        .bind(tenant_id)
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+        row.as_ref().map(map_subscription).transpose()
+    }
+
+    async fn save_subscription(&self, subscription: &Subscription) -> Result<()> {
+        let sources: Vec<&str> = subscription.sources.iter().map(|s| s.as_str()).collect();
+        sqlx::query(
+            "INSERT INTO public.webhook_subscriptions \
+             (id, tenant_id, name, url, secret, sources, event_types, active, created_by, created_at, updated_at) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11) \
+             ON CONFLICT (id) DO UPDATE SET \
+                 name = EXCLUDED.name, url = EXCLUDED.url, secret = EXCLUDED.secret, sources = EXCLUDED.sources, \
+                 event_types = EXCLUDED.event_types, active = EXCLUDED.active, updated_at = EXCLUDED.updated_at \
+             WHERE public.webhook_subscriptions.tenant_id = EXCLUDED.tenant_id",
+        )
+        .bind(subscription.id)
+        .bind(subscription.tenant_id)
+        .bind(&subscription.name)
+        .bind(&subscription.url)
+        .bind(&subscription.secret)
+        .bind(sources)
+        .bind(&subscription.event_types)
+        .bind(subscription.active)
+        .bind(subscription.created_by)
+        .bind(subscription.created_at)
+        .bind(subscription.updated_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn delete_subscription(&self, tenant_id: Uuid, id: Uuid) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM public.webhook_subscriptions WHERE tenant_id = $1 AND id = $2")
+            .bind(tenant_id)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn deliveries(&self, tenant_id: Uuid, query: &DeliveryQuery, limit: i64) -> Result<Vec<Delivery>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM public.webhook_deliveries d \
+             WHERE d.tenant_id = $1 \
+               AND ($2::TEXT IS NULL OR d.source = $2) \
+               AND ($3::TEXT IS NULL OR d.event_type = $3) \
+               AND ($4::TEXT IS NULL OR d.status = $4) \
+               AND ($5::UUID IS NULL OR d.subscription_id = $5) \
+               AND ($6::BIGINT IS NULL OR d.id < $6) \
+             ORDER BY d.id DESC LIMIT $7",
+            DELIVERY_COLUMNS
+        ))
+        .bind(tenant_id)
+        .bind(query.source.map(WebhookSource::as_str))
+        .bind(&query.event_type)
+        .bind(query.status.map(DeliveryStatus::as_str))
+        .bind(query.subscription_id)
+        .bind(query.before_id)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        rows.iter().map(map_delivery).collect()
+    }
+
+    async fn count_before(&self, tenant_id: Uuid, cutoff: DateTime<Utc>) -> Result<u64> {
+        let count: i64 = sqlx::query_scalar(&format!(
+            "SELECT COUNT(*) FROM public.webhook_deliveries d WHERE {}",
+            EXPIRED_DELIVERIES
+        ))
+        .bind(tenant_id)
+        .bind(cutoff)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(count as u64)
+    }
+
+    async fn delete_before(&self, tenant_id: Uuid, cutoff: DateTime<Utc>, limit: i64) -> Result<u64> {
+        let result = sqlx::query(&format!(
+            "DELETE FROM public.webhook_deliveries WHERE id IN ( \
+                 SELECT d.id FROM public.webhook_deliveries d WHERE {} ORDER BY d.created_at LIMIT $3)",
+            EXPIRED_DELIVERIES
+        ))
+        .bind(tenant_id)
+        .bind(cutoff)
+        .bind(limit)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+}
+
+/// Posts deliveries through the `webhooks` outbound integration
+pub struct OutboundTransport {
+    client: OutboundClient,
+}
+
+impl OutboundTransport {
+    pub fn new(client: OutboundClient) -> Self {
+        Self { client }
+    }
+}
+
+#[async_trait]
+impl WebhookTransport for OutboundTransport {
+    async fn post(&self, url: &str, headers: &[(&'static str, String)], body: String) -> Result<u16> {
+        let mut request = self.client.post(url).body(body);
+        for (name, value) in headers {
+            request = request.header(*name, value.as_str());
+        }
+        let response = self.client.send(request).await?;
+        Ok(response.status().as_u16())
+    }
+}
@@ -0,0 +1,97 @@
+// Admin lite: sign-in and actions for the server-rendered pages.
+// Loaded as a file so the pages work under the API's Content-Security-Policy.
+(function () {
+  "use strict";
+
+  var COOKIE = "admin_lite_token";
+
+  function token() {
+    var match = document.cookie.match(new RegExp("(?:^|; )" + COOKIE + "=([^;]*)"));
+    return match ? match[1] : null;
+  }
+
+  function setToken(value, maxAge) {
+    var secure = location.protocol === "https:" ? "; Secure" : "";
+    document.cookie = COOKIE + "=" + value + "; Path=/admin-lite; Max-Age=" + maxAge + "; SameSite=Strict" + secure;
+  }
+
+  function api(method, url, body) {
+    var headers = { "Accept": "application/json" };
+    var access = token();
+    if (access) headers["Authorization"] = "Bearer " + access;
+    if (body !== undefined) headers["Content-Type"] = "application/json";
+    return fetch(url, {
+      method: method,
+      headers: headers,
+      body: body === undefined ? undefined : JSON.stringify(body),
+      credentials: "same-origin"
+    }).then(function (response) {
+      if (!response.ok) throw new Error(method + " " + url + " failed with " + response.status);
+      return response.json();
+    });
+  }
+
+  function showError(element, error) {
+    if (!element) return;
+    element.textContent = error.message;
+    element.hidden = false;
+  }
+
+  function signIn(form) {
+    form.addEventListener("submit", function (event) {
+      event.preventDefault();
+      var error = form.querySelector(".error");
+      api("POST", "/api/v1/auth/login", {
+        email: form.elements.email.value,
+        password: form.elements.password.value
+      }).then(function (result) {
+        if (!result.access_token) throw new Error("Sign-in needs a second factor; use a token from the API");
+        setToken(result.access_token, 8 * 3600);
+        location.href = "/admin-lite/tenants";
+      }).catch(function (e) { showError(error, e); });
+    });
+  }
+
+  function recheck(button) {
+    button.addEventListener("click", function () {
+      button.disabled = true;
+      api("GET", button.dataset.url)
+        .then(function () { location.reload(); })
+        .catch(function (e) { button.disabled = false; button.textContent = e.message; });
+    });
+  }
+
+  function retentionPolicy(form) {
+    form.addEventListener("submit", function (event) {
+      event.preventDefault();
+      var error = form.querySelector(".error");
+      api("PUT", "/api/v1/admin/retention/policies", {
+        category: form.dataset.category,
+        retention_days: parseInt(form.elements.retention_days.value, 10),
+        action: form.elements.action.value
+      }).then(function () { location.reload(); })
+        .catch(function (e) { showError(error, e); });
+    });
+  }
+
+  function signOut(button) {
+    button.addEventListener("click", function () {
+      setToken("", 0);
+      location.href = "/admin-lite/login";
+    });
+  }
+
+  var actions = {
+    "sign-in": signIn,
+    "recheck": recheck,
+    "retention-policy": retentionPolicy,
+    "sign-out": signOut
+  };
+
+  document.addEventListener("DOMContentLoaded", function () {
+    document.querySelectorAll("[data-action]").forEach(function (element) {
+      var action = actions[element.dataset.action];
+      if (action) action(element);
+    });
+  });
+})();
@@ -0,0 +1,71 @@
+//! Two-factor backup codes.
+//!
+//! Enabling 2FA issues [`BACKUP_CODE_COUNT`] one-time codes for users who
+//! lost their authenticator app. Only their Argon2 hashes are kept, in
+//! `public.two_factor_backup_codes`; a code is marked used when it signs the
+//! user in and never matches again. Once fewer than [`LOW_BACKUP_CODES`] are
+//! left, a warning is audited with the [`NOTIFY_USER_TAG`] so the user can
+//! be told to generate new ones.
+
+use crate::session_limit::NOTIFY_USER_TAG;
+use erp_core::{
+    audit::{AuditEvent, AuditEventBuilder, EventOutcome, EventSeverity, EventType},
+    TenantContext,
+};
+use uuid::Uuid;
+
+/// Backup codes issued when 2FA is enabled
+pub const BACKUP_CODE_COUNT: usize = 8;
+
+/// Below this many unused codes the user is warned
+pub const LOW_BACKUP_CODES: usize = 2;
+
+/// Event type of the warning that few backup codes are left
+pub const LOW_BACKUP_CODES_EVENT: &str = "two_factor_backup_codes_low";
+
+/// Codes are compared without the spaces and dashes users type to group digits
+pub fn normalize(code: &str) -> String {
+    code.chars().filter(|c| !c.is_whitespace() && *c != '-').collect()
+}
+
+/// Audit event warning the user that only `remaining` backup codes are left
+pub fn low_backup_codes_event(tenant: &TenantContext, user_id: Uuid, remaining: usize) -> AuditEvent {
+    AuditEventBuilder::new(
+        EventType::Custom(LOW_BACKUP_CODES_EVENT.to_string()),
+        "Few two-factor backup codes left",
+    )
+    .severity(EventSeverity::Warning)
+    .outcome(EventOutcome::Success)
+    .tenant_id(tenant.tenant_id.0.to_string())
+    .actor_id(user_id.to_string())
+    .resource("user", user_id.to_string())
+    .metadata("remaining_backup_codes", serde_json::json!(remaining))
+    .tag(NOTIFY_USER_TAG)
+    .build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use erp_core::TenantId;
+
+    #[test]
+    fn test_grouping_is_ignored() {
+        assert_eq!(normalize(" 123-456 "), "123456");
+        assert_eq!(normalize("123 456"), "123456");
+    }
+
+    #[test]
+    fn test_low_codes_warning_notifies_the_user() {
+        let tenant = TenantContext { tenant_id: TenantId(Uuid::new_v4()), schema_name: "tenant_a".to_string() };
+        let user_id = Uuid::new_v4();
+
+        let event = low_backup_codes_event(&tenant, user_id, 1);
+
+        assert_eq!(event.event_type, EventType::Custom(LOW_BACKUP_CODES_EVENT.to_string()));
+        assert_eq!(event.severity, EventSeverity::Warning);
+        assert_eq!(event.actor_id, Some(user_id.to_string()));
+        assert_eq!(event.metadata["remaining_backup_codes"], serde_json::json!(1));
+        assert!(event.tags.iter().any(|tag| tag == NOTIFY_USER_TAG));
+    }
+}
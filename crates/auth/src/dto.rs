@@ -1,3 +1,4 @@
+use crate::models::AccountState;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use validator::Validate;
@@ -29,6 +30,9 @@ pub struct LoginResponse {
     pub access_token: String,
     #[serde(skip_serializing)]
     pub refresh_token: String,
+    /// Sessions the user holds including this one, so clients can warn near the cap
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub active_sessions: Option<u32>,
 }
 
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
@@ -38,10 +42,25 @@ pub struct TwoFactorRequiredResponse {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Validate, ToSchema)]
+#[validate(schema(function = "validate_second_factor"))]
 pub struct Verify2FARequest {
     pub login_session_token: String,
-    #[validate(length(equal = 6))]
+    /// Current code of the authenticator app; not needed with `backup_code`
+    #[serde(default)]
     pub code: String,
+    /// One of the backup codes issued when 2FA was enabled, used up by signing in
+    #[serde(default)]
+    pub backup_code: Option<String>,
+}
+
+/// A 6-digit authenticator code unless a backup code is given
+fn validate_second_factor(request: &Verify2FARequest) -> Result<(), validator::ValidationError> {
+    match &request.backup_code {
+        Some(backup_code) if backup_code.trim().is_empty() => Err(validator::ValidationError::new("backup_code")),
+        Some(_) => Ok(()),
+        None if request.code.len() != 6 => Err(validator::ValidationError::new("code")),
+        None => Ok(()),
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Validate, ToSchema)]
@@ -59,6 +78,16 @@ pub struct ResetPasswordRequest {
     pub confirm_password: String,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, Validate, ToSchema)]
+pub struct ChangePasswordRequest {
+    #[validate(length(min = 1))]
+    pub current_password: String,
+    #[validate(length(min = 8))]
+    pub new_password: String,
+    #[validate(length(min = 8))]
+    pub confirm_password: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Validate, ToSchema)]
 pub struct VerifyEmailRequest {
     pub token: String,
@@ -99,6 +128,36 @@ pub struct InviteUserRequest {
     pub last_name: Option<String>,
 }
 
+/// Invitation of a customer contact to a portal account
+#[derive(Debug, Clone, Serialize, Deserialize, Validate, ToSchema)]
+pub struct InvitePortalUserRequest {
+    pub customer_id: Uuid,
+    pub contact_id: Option<Uuid>,
+    #[validate(email)]
+    pub email: String,
+    pub first_name: Option<String>,
+    pub last_name: Option<String>,
+    /// Also see the customers below `customer_id` in the hierarchy
+    #[serde(default)]
+    pub include_children: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate, ToSchema)]
+pub struct AcceptPortalInvitationRequest {
+    pub token: String,
+    #[validate(length(min = 8))]
+    pub password: String,
+}
+
+/// Acceptance of a tenant user invitation; the token is in the path
+#[derive(Debug, Clone, Serialize, Deserialize, Validate, ToSchema)]
+pub struct AcceptInvitationRequest {
+    #[validate(length(min = 1))]
+    pub password: String,
+    #[validate(length(min = 1))]
+    pub confirm_password: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Validate, ToSchema)]
 pub struct UpdateUserRequest {
     pub first_name: Option<String>,
@@ -134,6 +193,9 @@ pub struct UserResponse {
     pub first_name: Option<String>,
     pub last_name: Option<String>,
     pub is_active: bool,
+    pub state: AccountState,
+    pub locked_until: Option<chrono::DateTime<chrono::Utc>>,
+    pub deleted_at: Option<chrono::DateTime<chrono::Utc>>,
     pub email_verified: bool,
     pub two_factor_enabled: bool,
     pub roles: Vec<RoleResponse>,
@@ -210,3 +272,48 @@ pub struct StopImpersonationResponse {
     pub original_user_id: Uuid,
 }
 
+
+// Session management DTOs
+/// One of a user's sign-in sessions
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SessionResponse {
+    pub session_id: String,
+    /// User agent of the client the session was opened from
+    pub user_agent: Option<String>,
+    pub device_fingerprint: Option<String>,
+    pub client_ip: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub last_activity: chrono::DateTime<chrono::Utc>,
+    pub expires_at: chrono::DateTime<chrono::Utc>,
+    /// The session of the token the request was made with ("this device")
+    pub current: bool,
+}
+
+impl SessionResponse {
+    /// The session as listed to a caller whose token belongs to `current_session`
+    pub fn of(session: &erp_core::session::SessionData, current_session: Option<&str>) -> Self {
+        Self {
+            session_id: session.session_id.clone(),
+            user_agent: session.user_agent.clone(),
+            device_fingerprint: session.device_fingerprint.clone(),
+            client_ip: session.client_ip.clone(),
+            created_at: session.created_at,
+            last_activity: session.last_activity,
+            expires_at: session.expires_at,
+            current: current_session == Some(session.session_id.as_str()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SessionListResponse {
+    pub success: bool,
+    /// Most recently active first
+    pub sessions: Vec<SessionResponse>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RevokeSessionsResponse {
+    pub success: bool,
+    pub revoked_sessions: u32,
+}
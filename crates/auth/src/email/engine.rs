@@ -0,0 +1,359 @@
+//! Sandboxed template engine for emails
+//!
+//! A small subset of Handlebars, enough for email wording and nothing that
+//! reaches outside the values it is given:
+//!
+//! - `{{name}}` outputs a variable, HTML-escaped unless rendered as plain text
+//! - `{{#if name}}…{{else}}…{{/if}}` and `{{#unless name}}…{{/unless}}`
+//! - `{{! comment }}` is dropped
+//!
+//! Unescaped output (`{{{name}}}`), partials, paths and helpers are rejected
+//! when the template is parsed, so a tenant's template can neither inject
+//! markup through a value nor touch files or the network.
+
+use serde_json::{Map, Value};
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// Values of the variables a template is rendered with
+pub type TemplateValues = Map<String, Value>;
+
+/// Why a template does not parse
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateSyntaxError {
+    /// 1-based line of the offending tag
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for TemplateSyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Text(String),
+    Variable(String),
+    Conditional {
+        variable: String,
+        negated: bool,
+        then: Vec<Node>,
+        otherwise: Vec<Node>,
+    },
+}
+
+/// How rendered values are written into the output
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Output {
+    /// HTML body: values are HTML-escaped
+    Html,
+    /// Text body: values as they are
+    Text,
+    /// Subject line: values with line breaks replaced, so no header can be added
+    Header,
+}
+
+/// A parsed template
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    nodes: Vec<Node>,
+}
+
+/// Open block while parsing
+struct Block {
+    variable: String,
+    negated: bool,
+    line: usize,
+    then: Vec<Node>,
+    otherwise: Option<Vec<Node>>,
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_lowercase() || c == '_')
+        && chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+}
+
+impl Template {
+    pub fn parse(source: &str) -> Result<Self, TemplateSyntaxError> {
+        let mut stack: Vec<Block> = Vec::new();
+        let mut nodes: Vec<Node> = Vec::new();
+        let mut rest = source;
+        let mut consumed = 0;
+
+        let line_at = |offset: usize| source[..offset].matches('\n').count() + 1;
+        let error = |offset: usize, message: String| TemplateSyntaxError {
+            line: line_at(offset),
+            message,
+        };
+
+        fn current<'a>(stack: &'a mut [Block], nodes: &'a mut Vec<Node>) -> &'a mut Vec<Node> {
+            match stack.last_mut() {
+                Some(block) => block.otherwise.as_mut().unwrap_or(&mut block.then),
+                None => nodes,
+            }
+        }
+
+        while let Some(start) = rest.find("{{") {
+            let offset = consumed + start;
+            if start > 0 {
+                current(&mut stack, &mut nodes).push(Node::Text(rest[..start].to_string()));
+            }
+            let after = &rest[start + 2..];
+            if after.starts_with('{') {
+                return Err(error(offset, "unescaped output `{{{…}}}` is not supported".to_string()));
+            }
+            let end = after
+                .find("}}")
+                .ok_or_else(|| error(offset, "`{{` is never closed".to_string()))?;
+            let tag = after[..end].trim();
+            rest = &after[end + 2..];
+            consumed = offset + 2 + end + 2;
+
+            if tag.starts_with('!') {
+                continue;
+            }
+            if let Some(block) = tag.strip_prefix('#') {
+                let mut parts = block.split_whitespace();
+                let (keyword, variable) = (parts.next().unwrap_or_default(), parts.next().unwrap_or_default());
+                if !matches!(keyword, "if" | "unless") {
+                    return Err(error(offset, format!("`{{{{#{}}}}}` is not supported; only #if and #unless are", keyword)));
+                }
+                if !is_identifier(variable) || parts.next().is_some() {
+                    return Err(error(offset, format!("`{{{{#{}}}}}` takes exactly one variable name", keyword)));
+                }
+                stack.push(Block {
+                    variable: variable.to_string(),
+                    negated: keyword == "unless",
+                    line: line_at(offset),
+                    then: Vec::new(),
+                    otherwise: None,
+                });
+                continue;
+            }
+            if let Some(keyword) = tag.strip_prefix('/') {
+                let block = stack
+                    .pop()
+                    .ok_or_else(|| error(offset, format!("`{{{{/{}}}}}` closes no block", keyword.trim())))?;
+                let expected = if block.negated { "unless" } else { "if" };
+                if keyword.trim() != expected {
+                    return Err(error(offset, format!("`{{{{/{}}}}}` closes a #{} block", keyword.trim(), expected)));
+                }
+                let node = Node::Conditional {
+                    variable: block.variable,
+                    negated: block.negated,
+                    then: block.then,
+                    otherwise: block.otherwise.unwrap_or_default(),
+                };
+                current(&mut stack, &mut nodes).push(node);
+                continue;
+            }
+            if tag == "else" {
+                match stack.last_mut() {
+                    Some(block) if block.otherwise.is_none() => block.otherwise = Some(Vec::new()),
+                    Some(_) => return Err(error(offset, "a block has only one `{{else}}`".to_string())),
+                    None => return Err(error(offset, "`{{else}}` outside of a block".to_string())),
+                }
+                continue;
+            }
+            if !is_identifier(tag) {
+                return Err(error(
+                    offset,
+                    format!("`{{{{{}}}}}` is not supported; write a variable name like `{{{{user_name}}}}`", tag),
+                ));
+            }
+            current(&mut stack, &mut nodes).push(Node::Variable(tag.to_string()));
+        }
+        if !rest.is_empty() {
+            current(&mut stack, &mut nodes).push(Node::Text(rest.to_string()));
+        }
+        if let Some(block) = stack.pop() {
+            return Err(TemplateSyntaxError {
+                line: block.line,
+                message: format!(
+                    "`{{{{#{} {}}}}}` is never closed",
+                    if block.negated { "unless" } else { "if" },
+                    block.variable
+                ),
+            });
+        }
+        Ok(Self { nodes })
+    }
+
+    /// Every variable the template refers to, in outputs and conditions
+    pub fn variables(&self) -> BTreeSet<String> {
+        fn collect(nodes: &[Node], into: &mut BTreeSet<String>) {
+            for node in nodes {
+                match node {
+                    Node::Text(_) => {}
+                    Node::Variable(name) => {
+                        into.insert(name.clone());
+                    }
+                    Node::Conditional { variable, then, otherwise, .. } => {
+                        into.insert(variable.clone());
+                        collect(then, into);
+                        collect(otherwise, into);
+                    }
+                }
+            }
+        }
+        let mut variables = BTreeSet::new();
+        collect(&self.nodes, &mut variables);
+        variables
+    }
+
+    /// Variables the template outputs, outside of conditions
+    pub fn outputs(&self) -> BTreeSet<String> {
+        fn collect(nodes: &[Node], into: &mut BTreeSet<String>) {
+            for node in nodes {
+                match node {
+                    Node::Text(_) => {}
+                    Node::Variable(name) => {
+                        into.insert(name.clone());
+                    }
+                    Node::Conditional { then, otherwise, .. } => {
+                        collect(then, into);
+                        collect(otherwise, into);
+                    }
+                }
+            }
+        }
+        let mut outputs = BTreeSet::new();
+        collect(&self.nodes, &mut outputs);
+        outputs
+    }
+
+    pub fn render(&self, values: &TemplateValues, output: Output) -> String {
+        let mut rendered = String::new();
+        render_nodes(&self.nodes, values, output, &mut rendered);
+        rendered
+    }
+}
+
+fn truthy(value: Option<&Value>) -> bool {
+    match value {
+        None | Some(Value::Null) => false,
+        Some(Value::Bool(b)) => *b,
+        Some(Value::String(s)) => !s.is_empty(),
+        Some(Value::Number(n)) => n.as_f64() != Some(0.0),
+        Some(Value::Array(items)) => !items.is_empty(),
+        Some(Value::Object(fields)) => !fields.is_empty(),
+    }
+}
+
+fn render_nodes(nodes: &[Node], values: &TemplateValues, output: Output, into: &mut String) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => into.push_str(text),
+            Node::Variable(name) => {
+                let value = match values.get(name) {
+                    None | Some(Value::Null) => String::new(),
+                    Some(Value::String(s)) => s.clone(),
+                    Some(other) => other.to_string(),
+                };
+                match output {
+                    Output::Html => into.push_str(&escape_html(&value)),
+                    Output::Text => into.push_str(&value),
+                    Output::Header => into.push_str(&value.replace(['\r', '\n'], " ")),
+                }
+            }
+            Node::Conditional { variable, negated, then, otherwise } => {
+                let branch = if truthy(values.get(variable)) != *negated { then } else { otherwise };
+                render_nodes(branch, values, output, into);
+            }
+        }
+    }
+}
+
+/// Escape text for HTML element content and quoted attribute values
+pub fn escape_html(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#x27;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn values(value: Value) -> TemplateValues {
+        value.as_object().unwrap().clone()
+    }
+
+    #[test]
+    fn test_values_are_escaped_in_html_only() {
+        let template = Template::parse("<p>Hi {{user_name}}</p>").unwrap();
+        let values = values(json!({ "user_name": "<script>alert('x')</script> & \"co\"" }));
+        assert_eq!(
+            template.render(&values, Output::Html),
+            "<p>Hi &lt;script&gt;alert(&#x27;x&#x27;)&lt;/script&gt; &amp; &quot;co&quot;</p>"
+        );
+        assert_eq!(template.render(&values, Output::Text), "<p>Hi <script>alert('x')</script> & \"co\"</p>");
+
+        // Attribute values cannot be broken out of
+        let link = Template::parse("<a href=\"{{url}}\">").unwrap();
+        let rendered = link.render(&self::values(json!({ "url": "x\" onclick=\"steal()" })), Output::Html);
+        assert_eq!(rendered, "<a href=\"x&quot; onclick=&quot;steal()\">");
+
+        let subject = Template::parse("Hello {{user_name}}").unwrap();
+        let rendered = subject.render(&self::values(json!({ "user_name": "Eve\r\nBcc: all@example.com" })), Output::Header);
+        assert_eq!(rendered, "Hello Eve  Bcc: all@example.com");
+    }
+
+    #[test]
+    fn test_conditionals_and_comments() {
+        let template =
+            Template::parse("{{! greeting }}{{#if reminder}}Reminder{{else}}Invite{{/if}} for {{name}}{{#unless ip}}.{{/unless}}")
+                .unwrap();
+        assert_eq!(
+            template.variables().into_iter().collect::<Vec<_>>(),
+            vec!["ip", "name", "reminder"]
+        );
+        assert_eq!(template.outputs().into_iter().collect::<Vec<_>>(), vec!["name"]);
+        assert_eq!(
+            template.render(&values(json!({ "reminder": true, "name": "Ann" })), Output::Text),
+            "Reminder for Ann."
+        );
+        assert_eq!(
+            template.render(&values(json!({ "reminder": false, "name": "Ann", "ip": "10.0.0.1" })), Output::Text),
+            "Invite for Ann"
+        );
+        // Numbers render as written, missing values as nothing
+        let hours = Template::parse("{{hours}} hours{{missing}}").unwrap();
+        assert_eq!(hours.render(&values(json!({ "hours": 24 })), Output::Html), "24 hours");
+    }
+
+    #[test]
+    fn test_unsafe_and_malformed_templates_are_rejected() {
+        for (source, line, message) in [
+            ("{{{user_name}}}", 1, "unescaped output"),
+            ("Hi\n{{> header}}", 2, "is not supported"),
+            ("{{lookup user name}}", 1, "is not supported"),
+            ("{{user.name}}", 1, "is not supported"),
+            ("{{#each users}}{{/each}}", 1, "only #if and #unless"),
+            ("{{#if a b}}{{/if}}", 1, "exactly one variable"),
+            ("{{#if a}}\nx", 1, "never closed"),
+            ("{{#if a}}{{/unless}}", 1, "closes a #if block"),
+            ("x{{/if}}", 1, "closes no block"),
+            ("{{else}}", 1, "outside of a block"),
+            ("{{#if a}}{{else}}{{else}}{{/if}}", 1, "only one"),
+            ("\n\n{{user_name", 3, "never closed"),
+        ] {
+            let error = Template::parse(source).unwrap_err();
+            assert_eq!(error.line, line, "{}", source);
+            assert!(error.message.contains(message), "{}: {}", source, error.message);
+        }
+    }
+}
@@ -1,12 +1,13 @@
-use crate::email::{EmailService, EmailTemplate};
+use crate::email::{EmailReference, EmailService, EmailTemplate};
 use erp_core::{
     audit::{AuditEvent, AuditLogger, EventType, EventSeverity, event::EventOutcome},
-    jobs::{Job, traits::JobContext, JobResult, SerializableJob},
+    jobs::{Job, traits::JobContext, JobHandler, JobResult, SerializableJob},
     Error, ErrorCode,
 };
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::Arc;
 use tracing::{error, info, warn};
 
 /// Email job data structure
@@ -30,6 +31,9 @@ pub struct EmailJobData {
     pub tenant_id: Option<String>,
     /// User ID for auditing
     pub user_id: Option<String>,
+    /// Customer the email is addressed to, if any; logged on the customer's communication history
+    #[serde(default)]
+    pub customer_id: Option<String>,
 }
 
 impl EmailJobData {
@@ -49,9 +53,27 @@ impl EmailJobData {
             max_retries: Some(3),
             tenant_id,
             user_id,
+            customer_id: None,
         }
     }
 
+    pub fn for_customer(mut self, customer_id: impl Into<String>) -> Self {
+        self.customer_id = Some(customer_id.into());
+        self
+    }
+
+    /// Reference passed to the email service; only set when both tenant and customer are known
+    fn reference(&self) -> Option<EmailReference> {
+        let tenant_id = self.tenant_id.as_deref()?.parse().ok()?;
+        let customer_id = self.customer_id.as_deref()?.parse().ok()?;
+        let reference = EmailReference::customer(tenant_id, customer_id);
+
+        Some(match self.user_id.as_deref().and_then(|id| id.parse().ok()) {
+            Some(user_id) => reference.sent_by(user_id),
+            None => reference,
+        })
+    }
+
     pub fn with_metadata(mut self, key: impl Into<String>, value: serde_json::Value) -> Self {
         self.metadata.insert(key.into(), value);
         self
@@ -66,14 +88,14 @@ impl EmailJobData {
 /// Background job for sending emails
 pub struct EmailJob {
     data: EmailJobData,
-    email_service: EmailService,
+    email_service: Arc<EmailService>,
     audit_logger: Option<AuditLogger>,
 }
 
 impl EmailJob {
     pub fn new(
         data: EmailJobData,
-        email_service: EmailService,
+        email_service: Arc<EmailService>,
         audit_logger: Option<AuditLogger>,
     ) -> Self {
         Self {
@@ -128,16 +150,20 @@ impl Job for EmailJob {
         }
 
         // Attempt to send email
-        match self.email_service.send_email(
+        let reference = self.data.reference();
+        match self.email_service.send_email_with_reference(
             &self.data.to,
             &self.data.subject,
             &self.data.html_body,
             Some(&self.data.text_body),
+            reference.as_ref(),
         ).await {
             Ok(_) => {
+                let template_source = self.data.metadata.get("template_source").and_then(|source| source.as_str());
                 info!(
                     job_id = %context.job_id,
                     template = %self.data.template_name,
+                    template_source = template_source.unwrap_or("built_in"),
                     recipient = %self.data.to,
                     "Email sent successfully"
                 );
@@ -149,6 +175,7 @@ impl Job for EmailJob {
                             .severity(EventSeverity::Info)
                             .outcome(EventOutcome::Success)
                             .metadata("template".to_string(), serde_json::Value::String(self.data.template_name.clone()))
+                            .metadata("template_source".to_string(), serde_json::Value::String(template_source.unwrap_or("built_in").to_string()))
                             .metadata("recipient".to_string(), serde_json::Value::String(self.data.to.clone()))
                             .metadata("subject".to_string(), serde_json::Value::String(self.data.subject.clone()))
                             .build()
@@ -252,6 +279,45 @@ impl Job for EmailJob {
     }
 }
 
+/// Runs queued [`EmailJobData`] with one shared [`EmailService`], so that all
+/// jobs send over the same pooled SMTP connections
+pub struct EmailJobHandler {
+    email_service: Arc<EmailService>,
+    audit_logger: Option<AuditLogger>,
+}
+
+impl EmailJobHandler {
+    pub fn new(email_service: Arc<EmailService>, audit_logger: Option<AuditLogger>) -> Self {
+        Self {
+            email_service,
+            audit_logger,
+        }
+    }
+}
+
+#[async_trait]
+impl JobHandler for EmailJobHandler {
+    fn job_type(&self) -> &'static str {
+        "email_send"
+    }
+
+    async fn handle(&self, job_data: &serde_json::Value, context: &JobContext) -> JobResult {
+        let data: EmailJobData = match serde_json::from_value(job_data.clone()) {
+            Ok(data) => data,
+            Err(e) => return JobResult::failed(format!("invalid email job: {}", e)),
+        };
+
+        EmailJob::new(data, self.email_service.clone(), self.audit_logger.clone())
+            .execute(context)
+            .await
+    }
+
+    fn validate_job_data(&self, job_data: &serde_json::Value) -> erp_core::Result<()> {
+        serde_json::from_value::<EmailJobData>(job_data.clone())?;
+        Ok(())
+    }
+}
+
 impl SerializableJob for EmailJobData {
     fn job_type(&self) -> &'static str {
         "email_send"
@@ -338,6 +404,7 @@ mod tests {
             max_retries: Some(3),
             tenant_id: None,
             user_id: None,
+            customer_id: None,
         };
 
         let serialized = <EmailJobData as erp_core::SerializableJob>::serialize(&job_data).unwrap();
@@ -347,4 +414,31 @@ mod tests {
         // so we can't directly compare, but we can test the serialization worked
         assert!(serialized.get("to").unwrap().as_str().unwrap() == "test@example.com");
     }
+
+    #[test]
+    fn test_customer_reference_requires_tenant_and_customer() {
+        let template = VerificationEmailTemplate {
+            user_name: "Test User".to_string(),
+            company_name: "Test Company".to_string(),
+            verification_url: "https://example.com/verify".to_string(),
+            expires_in_hours: 24,
+        };
+        let tenant_id = uuid::Uuid::new_v4();
+        let customer_id = uuid::Uuid::new_v4();
+
+        let job_data = EmailJobData::from_template("buyer@example.com", &template, Some(tenant_id.to_string()), None);
+        assert!(job_data.reference().is_none());
+
+        let reference = job_data.for_customer(customer_id.to_string()).reference().unwrap();
+        assert_eq!(reference, EmailReference::customer(tenant_id, customer_id));
+
+        // Jobs queued before the field existed still deserialize
+        let legacy = serde_json::json!({
+            "to": "a@example.com", "subject": "s", "html_body": "h", "text_body": "t",
+            "template_name": "n", "metadata": {}, "max_retries": null,
+            "tenant_id": null, "user_id": null
+        });
+        let parsed: EmailJobData = serde_json::from_value(legacy).unwrap();
+        assert!(parsed.customer_id.is_none());
+    }
 }
\ No newline at end of file
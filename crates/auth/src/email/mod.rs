@@ -1,8 +1,19 @@
+pub mod engine;
 pub mod jobs;
+pub mod observer;
+pub mod overrides;
 pub mod service;
 pub mod templates;
 
-pub use jobs::{EmailJob, EmailJobData};
+pub use jobs::{EmailJob, EmailJobData, EmailJobHandler};
+pub use observer::{EmailReference, SentEmail, SentEmailObserver};
+pub use overrides::{
+    email_job, normalize_locale, validate_override, EmailTemplateResolver, EmailTemplateStore, PostgresEmailTemplateStore, ResolvedEmail,
+    TemplateContent, TemplateIssue, TemplateOverride, TemplateSource,
+};
 pub use service::EmailService;
 pub use erp_core::config::EmailConfig;
-pub use templates::{EmailTemplate, VerificationEmailTemplate, PasswordResetEmailTemplate, WelcomeEmailTemplate};
\ No newline at end of file
+pub use templates::{
+    built_in, EmailTemplate, InvitationEmailTemplate, NamedTemplate, PasswordChangedEmailTemplate, PasswordResetEmailTemplate,
+    RenderedEmail, TemplatePart, VerificationEmailTemplate, WelcomeEmailTemplate, BUILT_IN_TEMPLATES,
+};
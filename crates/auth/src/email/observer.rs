@@ -0,0 +1,47 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+/// What an outgoing email is about, when the sender knows it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmailReference {
+    pub tenant_id: Uuid,
+    /// Customer the email was sent to or about
+    pub customer_id: Option<Uuid>,
+    /// User on whose behalf the email was sent; `None` for system emails
+    pub sent_by: Option<Uuid>,
+}
+
+impl EmailReference {
+    pub fn customer(tenant_id: Uuid, customer_id: Uuid) -> Self {
+        Self {
+            tenant_id,
+            customer_id: Some(customer_id),
+            sent_by: None,
+        }
+    }
+
+    pub fn sent_by(mut self, user_id: Uuid) -> Self {
+        self.sent_by = Some(user_id);
+        self
+    }
+}
+
+/// An email that was handed to the provider successfully
+#[derive(Debug, Clone)]
+pub struct SentEmail {
+    pub to: String,
+    pub subject: String,
+    pub text_body: Option<String>,
+    pub reference: EmailReference,
+    pub sent_at: DateTime<Utc>,
+}
+
+/// Notified after every successfully sent email that carries a reference.
+///
+/// Observers run after delivery; a failing observer is logged by the
+/// implementation and never fails the send itself.
+#[async_trait]
+pub trait SentEmailObserver: Send + Sync {
+    async fn email_sent(&self, email: &SentEmail);
+}
@@ -0,0 +1,592 @@
+//! Tenant overrides of the email templates
+//!
+//! A tenant may store its own subject, HTML body and text body for a named
+//! template (see [`super::templates::BUILT_IN_TEMPLATES`]), for one locale or
+//! as its default. Overrides are checked against the template's declared
+//! variables when they are saved: unknown variables are rejected and required
+//! ones must be output in both bodies.
+//!
+//! Sending resolves the wording through the chain tenant + locale, tenant
+//! default, built-in; the locale is the tenant's `email_locale`. The source
+//! chosen is logged with every email and kept in the job's metadata.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use erp_core::{Error, ErrorCode, Result};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Row};
+use std::sync::Arc;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use super::engine::{Template, TemplateValues};
+use super::jobs::EmailJobData;
+use super::templates::{built_in, EmailTemplate, NamedTemplate, RenderedEmail, TemplatePart};
+
+pub const MAX_SUBJECT_LENGTH: usize = 255;
+pub const MAX_BODY_LENGTH: usize = 100_000;
+
+/// Wording of a template as a tenant writes it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TemplateContent {
+    pub subject: String,
+    pub html_body: String,
+    pub text_body: String,
+}
+
+impl TemplateContent {
+    pub fn source(&self, part: TemplatePart) -> &str {
+        match part {
+            TemplatePart::Subject => &self.subject,
+            TemplatePart::HtmlBody => &self.html_body,
+            TemplatePart::TextBody => &self.text_body,
+        }
+    }
+
+    /// Render with `values`; the content is expected to have been validated
+    pub fn render(&self, values: &TemplateValues) -> Result<RenderedEmail> {
+        RenderedEmail::render(&self.subject, &self.html_body, &self.text_body, values)
+            .map_err(|e| Error::new(ErrorCode::ValidationFailed, format!("Template does not parse: {}", e)))
+    }
+}
+
+/// A tenant's stored wording of a named template
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TemplateOverride {
+    pub template_name: String,
+    /// `None` is the tenant's default for every locale
+    pub locale: Option<String>,
+    #[serde(flatten)]
+    pub content: TemplateContent,
+    pub updated_by: Option<Uuid>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Why an override cannot be saved
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TemplateIssue {
+    pub part: TemplatePart,
+    pub message: String,
+}
+
+/// Check an override of `template` against its declared variables
+pub fn validate_override(template: &NamedTemplate, content: &TemplateContent) -> Vec<TemplateIssue> {
+    let mut issues = Vec::new();
+    let mut issue = |part, message: String| issues.push(TemplateIssue { part, message });
+
+    if content.subject.trim().is_empty() {
+        issue(TemplatePart::Subject, "The subject is empty".to_string());
+    }
+    for part in TemplatePart::ALL {
+        let source = content.source(part);
+        let max = match part {
+            TemplatePart::Subject => MAX_SUBJECT_LENGTH,
+            _ => MAX_BODY_LENGTH,
+        };
+        if source.len() > max {
+            issue(part, format!("Longer than {} characters", max));
+            continue;
+        }
+
+        let parsed = match Template::parse(source) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                issue(part, e.to_string());
+                continue;
+            }
+        };
+        for name in parsed.variables() {
+            if template.variable(&name).is_none() {
+                issue(part, format!("Unknown variable `{}`", name));
+            }
+        }
+        if part != TemplatePart::Subject {
+            let outputs = parsed.outputs();
+            for variable in template.variables.iter().filter(|v| v.required) {
+                if !outputs.contains(variable.name) {
+                    issue(part, format!("Required variable `{}` is missing", variable.name));
+                }
+            }
+        }
+    }
+    issues
+}
+
+/// `de`, `de-AT` or `pt_br` as `de`, `de-AT` and `pt-BR`; `None` if it is no locale
+pub fn normalize_locale(locale: &str) -> Option<String> {
+    let mut parts = locale.trim().split(['-', '_']);
+    let language = parts.next()?.to_ascii_lowercase();
+    if !(2..=3).contains(&language.len()) || !language.chars().all(|c| c.is_ascii_lowercase()) {
+        return None;
+    }
+    match (parts.next(), parts.next()) {
+        (None, _) => Some(language),
+        (Some(region), None) if region.len() == 2 && region.chars().all(|c| c.is_ascii_alphabetic()) => {
+            Some(format!("{}-{}", language, region.to_ascii_uppercase()))
+        }
+        _ => None,
+    }
+}
+
+/// Where the wording of a sent email came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TemplateSource {
+    /// The tenant's override for the locale
+    TenantLocale,
+    /// The tenant's override without a locale
+    TenantDefault,
+    BuiltIn,
+}
+
+impl TemplateSource {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TemplateSource::TenantLocale => "tenant_locale",
+            TemplateSource::TenantDefault => "tenant_default",
+            TemplateSource::BuiltIn => "built_in",
+        }
+    }
+}
+
+/// An email rendered through the fallback chain
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ResolvedEmail {
+    pub source: TemplateSource,
+    pub locale: Option<String>,
+    #[serde(flatten)]
+    pub email: RenderedEmail,
+}
+
+/// Where tenant overrides are kept
+#[async_trait]
+pub trait EmailTemplateStore: Send + Sync {
+    /// Locale the tenant's emails are sent in, if it chose one
+    async fn tenant_locale(&self, tenant_id: Uuid) -> Result<Option<String>>;
+
+    /// The override for exactly `locale`; `None` is the tenant default
+    async fn find(&self, tenant_id: Uuid, template_name: &str, locale: Option<&str>) -> Result<Option<TemplateOverride>>;
+
+    async fn list(&self, tenant_id: Uuid) -> Result<Vec<TemplateOverride>>;
+
+    /// Store the override, replacing the one of the same template and locale
+    async fn save(&self, tenant_id: Uuid, template: &TemplateOverride) -> Result<()>;
+
+    /// Whether there was an override to delete
+    async fn delete(&self, tenant_id: Uuid, template_name: &str, locale: Option<&str>) -> Result<bool>;
+}
+
+/// Overrides in `public.email_template_overrides`
+pub struct PostgresEmailTemplateStore {
+    pool: PgPool,
+}
+
+impl PostgresEmailTemplateStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+fn override_from_row(row: &sqlx::postgres::PgRow) -> Result<TemplateOverride> {
+    Ok(TemplateOverride {
+        template_name: row.try_get("template_name")?,
+        locale: row.try_get("locale")?,
+        content: TemplateContent {
+            subject: row.try_get("subject")?,
+            html_body: row.try_get("html_body")?,
+            text_body: row.try_get("text_body")?,
+        },
+        updated_by: row.try_get("updated_by")?,
+        updated_at: row.try_get("updated_at")?,
+    })
+}
+
+#[async_trait]
+impl EmailTemplateStore for PostgresEmailTemplateStore {
+    async fn tenant_locale(&self, tenant_id: Uuid) -> Result<Option<String>> {
+        let locale: Option<Option<String>> =
+            sqlx::query_scalar("SELECT email_locale FROM public.tenants WHERE id = $1")
+                .bind(tenant_id)
+                .fetch_optional(&self.pool)
+                .await?;
+        Ok(locale.flatten())
+    }
+
+    async fn find(&self, tenant_id: Uuid, template_name: &str, locale: Option<&str>) -> Result<Option<TemplateOverride>> {
+        let row = sqlx::query(
+            "SELECT template_name, locale, subject, html_body, text_body, updated_by, updated_at \
+             FROM public.email_template_overrides \
+             WHERE tenant_id = $1 AND template_name = $2 AND locale IS NOT DISTINCT FROM $3",
+        )
+        .bind(tenant_id)
+        .bind(template_name)
+        .bind(locale)
+        .fetch_optional(&self.pool)
+        .await?;
+        row.as_ref().map(override_from_row).transpose()
+    }
+
+    async fn list(&self, tenant_id: Uuid) -> Result<Vec<TemplateOverride>> {
+        let rows = sqlx::query(
+            "SELECT template_name, locale, subject, html_body, text_body, updated_by, updated_at \
+             FROM public.email_template_overrides WHERE tenant_id = $1 \
+             ORDER BY template_name, locale NULLS FIRST",
+        )
+        .bind(tenant_id)
+        .fetch_all(&self.pool)
+        .await?;
+        rows.iter().map(override_from_row).collect()
+    }
+
+    async fn save(&self, tenant_id: Uuid, template: &TemplateOverride) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO public.email_template_overrides \
+                 (tenant_id, template_name, locale, subject, html_body, text_body, updated_by, updated_at) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8) \
+             ON CONFLICT (tenant_id, template_name, (COALESCE(locale, ''))) DO UPDATE SET \
+                 subject = EXCLUDED.subject, html_body = EXCLUDED.html_body, text_body = EXCLUDED.text_body, \
+                 updated_by = EXCLUDED.updated_by, updated_at = EXCLUDED.updated_at",
+        )
+        .bind(tenant_id)
+        .bind(&template.template_name)
+        .bind(&template.locale)
+        .bind(&template.content.subject)
+        .bind(&template.content.html_body)
+        .bind(&template.content.text_body)
+        .bind(template.updated_by)
+        .bind(template.updated_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn delete(&self, tenant_id: Uuid, template_name: &str, locale: Option<&str>) -> Result<bool> {
+        let result = sqlx::query(
+            "DELETE FROM public.email_template_overrides \
+             WHERE tenant_id = $1 AND template_name = $2 AND locale IS NOT DISTINCT FROM $3",
+        )
+        .bind(tenant_id)
+        .bind(template_name)
+        .bind(locale)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+/// Renders emails with the tenant's overrides, falling back to the built-in wording
+pub struct EmailTemplateResolver {
+    store: Arc<dyn EmailTemplateStore>,
+}
+
+impl EmailTemplateResolver {
+    pub fn new(store: Arc<dyn EmailTemplateStore>) -> Self {
+        Self { store }
+    }
+
+    pub fn store(&self) -> &Arc<dyn EmailTemplateStore> {
+        &self.store
+    }
+
+    /// The override the chain picks for `locale`: the tenant's for the
+    /// locale, else the tenant default; `None` means the built-in wording
+    pub async fn resolve(
+        &self,
+        tenant_id: Uuid,
+        template_name: &str,
+        locale: Option<&str>,
+    ) -> Result<(TemplateSource, Option<TemplateOverride>)> {
+        if let Some(locale) = locale {
+            if let Some(found) = self.store.find(tenant_id, template_name, Some(locale)).await? {
+                return Ok((TemplateSource::TenantLocale, Some(found)));
+            }
+        }
+        match self.store.find(tenant_id, template_name, None).await? {
+            Some(found) => Ok((TemplateSource::TenantDefault, Some(found))),
+            None => Ok((TemplateSource::BuiltIn, None)),
+        }
+    }
+
+    /// Render `template` of `name` with `values` through the chain for `locale`
+    pub async fn render_named(
+        &self,
+        tenant_id: Uuid,
+        template: &NamedTemplate,
+        locale: Option<&str>,
+        values: &TemplateValues,
+    ) -> Result<ResolvedEmail> {
+        let (source, found) = self.resolve(tenant_id, template.name, locale).await?;
+        let email = match found {
+            Some(found) => found.content.render(values)?,
+            None => RenderedEmail::render(template.subject, template.html_body, template.text_body, values)
+                .map_err(|e| Error::new(ErrorCode::InternalServerError, e.to_string()))?,
+        };
+        Ok(ResolvedEmail {
+            source,
+            locale: locale.map(str::to_string),
+            email,
+        })
+    }
+
+    /// Render `template` for the tenant in its email locale. A store that
+    /// cannot be read or an override that no longer renders falls back to the
+    /// built-in wording, so the email still goes out.
+    pub async fn render(&self, tenant_id: Uuid, template: &dyn EmailTemplate) -> ResolvedEmail {
+        let built_in_email = || ResolvedEmail {
+            source: TemplateSource::BuiltIn,
+            locale: None,
+            email: RenderedEmail {
+                subject: template.subject(),
+                html_body: template.html_body(),
+                text_body: template.text_body(),
+            },
+        };
+        let Some(named) = built_in(template.registered_name()) else {
+            return built_in_email();
+        };
+
+        let resolved = async {
+            let locale = self.store.tenant_locale(tenant_id).await?;
+            self.render_named(tenant_id, named, locale.as_deref(), &template.variables()).await
+        };
+        let resolved = match resolved.await {
+            Ok(resolved) => resolved,
+            Err(e) => {
+                warn!(
+                    tenant_id = %tenant_id,
+                    template = template.template_name(),
+                    error = %e,
+                    "Email template override failed, using the built-in template"
+                );
+                built_in_email()
+            }
+        };
+        info!(
+            tenant_id = %tenant_id,
+            template = template.template_name(),
+            source = resolved.source.as_str(),
+            locale = resolved.locale.as_deref().unwrap_or("-"),
+            "Email template resolved"
+        );
+        resolved
+    }
+}
+
+/// Job data sending `template` to `to`, in the tenant's wording when there are `templates`
+pub async fn email_job(
+    templates: Option<&EmailTemplateResolver>,
+    to: &str,
+    template: &dyn EmailTemplate,
+    tenant_id: Uuid,
+    user_id: Option<String>,
+) -> EmailJobData {
+    let job = EmailJobData::from_template(to, template, Some(tenant_id.to_string()), user_id);
+    let Some(templates) = templates else {
+        return job.with_metadata("template_source", TemplateSource::BuiltIn.as_str().into());
+    };
+    let resolved = templates.render(tenant_id, template).await;
+    EmailJobData {
+        subject: resolved.email.subject,
+        html_body: resolved.email.html_body,
+        text_body: resolved.email.text_body,
+        ..job
+    }
+    .with_metadata("template_source", resolved.source.as_str().into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::email::templates::BUILT_IN_TEMPLATES;
+    use crate::email::VerificationEmailTemplate;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct MemoryStore {
+        locale: Option<String>,
+        overrides: Mutex<HashMap<(String, Option<String>), TemplateOverride>>,
+        broken: bool,
+    }
+
+    impl MemoryStore {
+        fn put(&self, locale: Option<&str>, subject: &str) {
+            let template = TemplateOverride {
+                template_name: "email_verification".to_string(),
+                locale: locale.map(str::to_string),
+                content: TemplateContent {
+                    subject: subject.to_string(),
+                    html_body: "<a href=\"{{verification_url}}\">{{user_name}}</a>".to_string(),
+                    text_body: "{{verification_url}}".to_string(),
+                },
+                updated_by: None,
+                updated_at: Utc::now(),
+            };
+            self.overrides
+                .lock()
+                .unwrap()
+                .insert((template.template_name.clone(), template.locale.clone()), template);
+        }
+    }
+
+    #[async_trait]
+    impl EmailTemplateStore for MemoryStore {
+        async fn tenant_locale(&self, _tenant_id: Uuid) -> Result<Option<String>> {
+            if self.broken {
+                return Err(Error::new(ErrorCode::DatabaseError, "connection lost"));
+            }
+            Ok(self.locale.clone())
+        }
+
+        async fn find(&self, _tenant_id: Uuid, template_name: &str, locale: Option<&str>) -> Result<Option<TemplateOverride>> {
+            let key = (template_name.to_string(), locale.map(str::to_string));
+            Ok(self.overrides.lock().unwrap().get(&key).cloned())
+        }
+
+        async fn list(&self, _tenant_id: Uuid) -> Result<Vec<TemplateOverride>> {
+            Ok(self.overrides.lock().unwrap().values().cloned().collect())
+        }
+
+        async fn save(&self, _tenant_id: Uuid, template: &TemplateOverride) -> Result<()> {
+            let key = (template.template_name.clone(), template.locale.clone());
+            self.overrides.lock().unwrap().insert(key, template.clone());
+            Ok(())
+        }
+
+        async fn delete(&self, _tenant_id: Uuid, template_name: &str, locale: Option<&str>) -> Result<bool> {
+            let key = (template_name.to_string(), locale.map(str::to_string));
+            Ok(self.overrides.lock().unwrap().remove(&key).is_some())
+        }
+    }
+
+    fn verification() -> VerificationEmailTemplate {
+        VerificationEmailTemplate {
+            user_name: "<b>Jane</b>".to_string(),
+            company_name: "Acme Corp".to_string(),
+            verification_url: "https://example.com/verify?token=abc&x=1".to_string(),
+            expires_in_hours: 24,
+        }
+    }
+
+    fn content(subject: &str, html_body: &str, text_body: &str) -> TemplateContent {
+        TemplateContent {
+            subject: subject.to_string(),
+            html_body: html_body.to_string(),
+            text_body: text_body.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_built_in_templates_pass_their_own_schema() {
+        for template in &BUILT_IN_TEMPLATES {
+            let content = content(template.subject, template.html_body, template.text_body);
+            assert_eq!(validate_override(template, &content), Vec::new(), "{}", template.name);
+        }
+    }
+
+    #[test]
+    fn test_override_validated_against_variable_schema() {
+        let template = built_in("email_verification").unwrap();
+        assert!(validate_override(template, &content(
+            "Bitte bestätigen, {{user_name}}",
+            "<p>{{#if user_name}}Hallo {{user_name}}{{/if}}</p><a href=\"{{verification_url}}\">Bestätigen</a>",
+            "Bestätigen: {{verification_url}}",
+        ))
+        .is_empty());
+
+        let issues = validate_override(template, &content(
+            "Hi {{first_name}}",
+            "<p>Hello</p>{{#if verification_url}}{{/if}}",
+            "{{verification_url}} {{{user_name}}}",
+        ));
+        assert_eq!(
+            issues,
+            vec![
+                TemplateIssue { part: TemplatePart::Subject, message: "Unknown variable `first_name`".to_string() },
+                // Only tested, never output
+                TemplateIssue {
+                    part: TemplatePart::HtmlBody,
+                    message: "Required variable `verification_url` is missing".to_string(),
+                },
+                TemplateIssue {
+                    part: TemplatePart::TextBody,
+                    message: "line 1: unescaped output `{{{…}}}` is not supported".to_string(),
+                },
+            ]
+        );
+
+        let issues = validate_override(template, &content(" ", "{{verification_url}}", &"x".repeat(MAX_BODY_LENGTH + 1)));
+        assert_eq!(issues.len(), 2);
+        assert_eq!(issues[0].message, "The subject is empty");
+        assert_eq!(issues[1].part, TemplatePart::TextBody);
+    }
+
+    #[test]
+    fn test_normalize_locale() {
+        assert_eq!(normalize_locale("de").as_deref(), Some("de"));
+        assert_eq!(normalize_locale("DE-at").as_deref(), Some("de-AT"));
+        assert_eq!(normalize_locale("pt_br").as_deref(), Some("pt-BR"));
+        for invalid in ["", "d", "german", "de-AUT", "de-AT-x", "d3"] {
+            assert_eq!(normalize_locale(invalid), None, "{}", invalid);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fallback_chain() {
+        let tenant_id = Uuid::from_u128(1);
+        let store = Arc::new(MemoryStore {
+            locale: Some("de".to_string()),
+            ..MemoryStore::default()
+        });
+        let resolver = EmailTemplateResolver::new(store.clone());
+
+        // Nothing stored: the built-in wording
+        let resolved = resolver.render(tenant_id, &verification()).await;
+        assert_eq!(resolved.source, TemplateSource::BuiltIn);
+        assert_eq!(resolved.email.subject, "Verify your account for Acme Corp");
+
+        // A default for every locale
+        store.put(None, "Default for {{company_name}}");
+        let resolved = resolver.render(tenant_id, &verification()).await;
+        assert_eq!(resolved.source, TemplateSource::TenantDefault);
+        assert_eq!(resolved.email.subject, "Default for Acme Corp");
+        // Values escaped in the tenant's HTML too
+        assert_eq!(
+            resolved.email.html_body,
+            "<a href=\"https://example.com/verify?token=abc&amp;x=1\">&lt;b&gt;Jane&lt;/b&gt;</a>"
+        );
+        assert_eq!(resolved.email.text_body, "https://example.com/verify?token=abc&x=1");
+
+        // The tenant's locale wins over its default; other locales do not apply
+        store.put(Some("fr"), "Français");
+        assert_eq!(resolver.render(tenant_id, &verification()).await.source, TemplateSource::TenantDefault);
+        store.put(Some("de"), "Deutsch");
+        let resolved = resolver.render(tenant_id, &verification()).await;
+        assert_eq!(resolved.source, TemplateSource::TenantLocale);
+        assert_eq!(resolved.locale.as_deref(), Some("de"));
+        assert_eq!(resolved.email.subject, "Deutsch");
+
+        // The job carries the chosen wording and its source
+        let job = email_job(Some(&resolver), "jane@example.com", &verification(), tenant_id, None).await;
+        assert_eq!(job.subject, "Deutsch");
+        assert_eq!(job.template_name, "email_verification");
+        assert_eq!(job.metadata["template_source"], "tenant_locale");
+
+        let job = email_job(None, "jane@example.com", &verification(), tenant_id, None).await;
+        assert_eq!(job.subject, "Verify your account for Acme Corp");
+        assert_eq!(job.metadata["template_source"], "built_in");
+    }
+
+    #[tokio::test]
+    async fn test_unreadable_store_falls_back_to_built_in() {
+        let store = Arc::new(MemoryStore {
+            broken: true,
+            ..MemoryStore::default()
+        });
+        store.put(None, "Never used");
+        let resolved = EmailTemplateResolver::new(store).render(Uuid::from_u128(1), &verification()).await;
+        assert_eq!(resolved.source, TemplateSource::BuiltIn);
+        assert_eq!(resolved.email.subject, "Verify your account for Acme Corp");
+        assert!(resolved.email.html_body.contains("Hi &lt;b&gt;Jane&lt;/b&gt;,"));
+    }
+}
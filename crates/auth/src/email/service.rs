@@ -1,34 +1,66 @@
-use erp_core::{Error, ErrorCode, Result, config::EmailConfig};
+use erp_core::{
+    audit::{event::EventOutcome, AuditEvent, AuditLogger, EventSeverity, EventType},
+    config::{EmailConfig, SmtpPoolConfig},
+    Error, ErrorCode, Result,
+};
 use lettre::{
     message::header::ContentType,
-    transport::smtp::{authentication::Credentials, client::Tls},
+    transport::smtp::{
+        self,
+        authentication::Credentials,
+        client::{Tls, TlsParameters},
+        PoolConfig,
+    },
     AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
 };
 use serde::{Deserialize, Serialize};
-use std::time::Duration;
+use std::{fmt, sync::Arc, time::Duration};
 use tracing::{debug, error, info, warn};
 
+use super::observer::{EmailReference, SentEmail, SentEmailObserver};
+
 /// Email service providers
 #[derive(Debug, Clone, Copy)]
 pub enum EmailProvider {
     Mock,
+    /// Builds every message but records it in the audit log instead of sending it; for staging
+    Log,
     Smtp,
     SendGrid,
     AwsSes,
 }
 
-/// Email service for sending emails
-#[derive(Debug)]
+/// Email service for sending emails.
+///
+/// The SMTP transport keeps a pool of open connections (see
+/// [`SmtpPoolConfig`]), so one service should be shared by everything that
+/// sends, the email jobs included, rather than created per message. The pool
+/// runs on Tokio, so SMTP-backed services must be created inside a runtime.
 pub struct EmailService {
     config: EmailConfig,
     provider: EmailProvider,
     smtp_transport: Option<AsyncSmtpTransport<Tokio1Executor>>,
+    observers: Vec<Arc<dyn SentEmailObserver>>,
+    audit_logger: Option<AuditLogger>,
+}
+
+impl fmt::Debug for EmailService {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EmailService")
+            .field("config", &self.config)
+            .field("provider", &self.provider)
+            .field("smtp_transport", &self.smtp_transport)
+            .field("observers", &self.observers.len())
+            .field("audit_logger", &self.audit_logger.is_some())
+            .finish()
+    }
 }
 
 impl EmailService {
     pub fn new(config: EmailConfig) -> Result<Self> {
         let provider = match config.provider.as_str() {
             "mock" => EmailProvider::Mock,
+            "log" => EmailProvider::Log,
             "smtp" => EmailProvider::Smtp,
             "sendgrid" => EmailProvider::SendGrid,
             "aws_ses" => EmailProvider::AwsSes,
@@ -41,7 +73,7 @@ impl EmailService {
         };
 
         let smtp_transport = match provider {
-            EmailProvider::Mock => None,
+            EmailProvider::Mock | EmailProvider::Log => None,
             EmailProvider::Smtp => Some(Self::create_smtp_transport(&config)?),
             EmailProvider::SendGrid => {
                 // SendGrid uses SMTP with API key
@@ -57,9 +89,23 @@ impl EmailService {
             config,
             provider,
             smtp_transport,
+            observers: Vec::new(),
+            audit_logger: None,
         })
     }
 
+    /// Register an observer that is told about every sent email with a reference
+    pub fn with_observer(mut self, observer: Arc<dyn SentEmailObserver>) -> Self {
+        self.observers.push(observer);
+        self
+    }
+
+    /// Audit log the `log` provider records messages to; without one it only traces them
+    pub fn with_audit_logger(mut self, audit_logger: AuditLogger) -> Self {
+        self.audit_logger = Some(audit_logger);
+        self
+    }
+
     /// Create a mock email service for testing
     pub fn mock() -> Self {
         Self {
@@ -71,8 +117,10 @@ impl EmailService {
                 smtp_password: None,
                 smtp_from_email: "test@localhost".to_string(),
                 smtp_from_name: "Test System".to_string(),
+                smtp_reply_to: None,
                 use_tls: false,
                 use_starttls: false,
+                smtp_pool: SmtpPoolConfig::default(),
                 timeout_seconds: 30,
                 max_retries: 1,
                 sendgrid_api_key: None,
@@ -82,6 +130,8 @@ impl EmailService {
             },
             provider: EmailProvider::Mock,
             smtp_transport: None,
+            observers: Vec::new(),
+            audit_logger: None,
         }
     }
 
@@ -92,6 +142,18 @@ impl EmailService {
         subject: &str,
         html_body: &str,
         text_body: Option<&str>,
+    ) -> Result<()> {
+        self.send_email_with_reference(to, subject, html_body, text_body, None).await
+    }
+
+    /// Send an email and, once delivered, notify observers about what it referred to
+    pub async fn send_email_with_reference(
+        &self,
+        to: &str,
+        subject: &str,
+        html_body: &str,
+        text_body: Option<&str>,
+        reference: Option<&EmailReference>,
     ) -> Result<()> {
         info!(
             provider = ?self.provider,
@@ -122,12 +184,45 @@ impl EmailService {
                     to, subject
                 );
                 // Use the simulate_email_send method for mock provider
-                self.simulate_email_send(to, subject, html_body, text_body).await
+                self.simulate_email_send(to, subject, html_body, text_body).await?
+            },
+            EmailProvider::Log => {
+                self.record_email(to, subject, html_body, text_body).await?
             },
             EmailProvider::Smtp | EmailProvider::SendGrid | EmailProvider::AwsSes => {
-                self.send_via_smtp(to, subject, html_body, text_body).await
+                self.send_via_smtp(to, subject, html_body, text_body).await?
             }
         }
+
+        if let Some(reference) = reference {
+            self.notify_observers(to, subject, text_body, reference).await;
+        }
+
+        Ok(())
+    }
+
+    async fn notify_observers(
+        &self,
+        to: &str,
+        subject: &str,
+        text_body: Option<&str>,
+        reference: &EmailReference,
+    ) {
+        if self.observers.is_empty() {
+            return;
+        }
+
+        let sent = SentEmail {
+            to: to.to_string(),
+            subject: subject.to_string(),
+            text_body: text_body.map(str::to_string),
+            reference: reference.clone(),
+            sent_at: chrono::Utc::now(),
+        };
+
+        for observer in &self.observers {
+            observer.email_sent(&sent).await;
+        }
     }
 
     /// Send a bulk email to multiple recipients
@@ -165,7 +260,7 @@ impl EmailService {
     pub async fn test_connection(&self) -> Result<()> {
         info!("Testing email service connection");
         
-        if self.smtp_transport.is_none() {
+        let Some(transport) = &self.smtp_transport else {
             debug!("Mock email service: testing connection with simulate_email_send");
             // Use simulate_email_send to test mock functionality
             return self.simulate_email_send(
@@ -174,29 +269,22 @@ impl EmailService {
                 "<p>This is a connection test email</p>",
                 Some("This is a connection test email")
             ).await;
-        }
-
-        // In a real implementation, you would test the SMTP connection
-        // For now, we'll just validate the configuration
-        if self.config.smtp_host.as_ref().map_or(true, |h| h.is_empty()) {
-            return Err(Error::new(
-                ErrorCode::ConfigurationError,
-                "SMTP host is not configured"
-            ));
-        }
+        };
 
-        if self.config.smtp_username.as_ref().map_or(true, |u| u.is_empty()) {
-            return Err(Error::new(
-                ErrorCode::ConfigurationError,
-                "SMTP username is not configured"
-            ));
+        match transport.test_connection().await {
+            Ok(true) => {
+                info!("Email service connection test passed");
+                Ok(())
+            }
+            Ok(false) => Err(Error::new(
+                ErrorCode::ServiceUnavailable,
+                "SMTP server did not answer the connection test"
+            )),
+            Err(e) => Err(Error::new(
+                smtp_error_code(&e),
+                format!("SMTP connection test failed: {}", e)
+            )),
         }
-
-        // Simulate connection test
-        tokio::time::sleep(Duration::from_millis(100)).await;
-        
-        info!("Email service connection test passed");
-        Ok(())
     }
 
     /// Send email via SMTP
@@ -210,42 +298,12 @@ impl EmailService {
         let transport = self.smtp_transport.as_ref()
             .ok_or_else(|| Error::new(ErrorCode::ConfigurationError, "SMTP transport not configured"))?;
 
-        // Build email message
-        let message_builder = Message::builder()
-            .from(format!("{} <{}>", self.config.smtp_from_name, self.config.smtp_from_email).parse()
-                .map_err(|e| Error::new(ErrorCode::ValidationFailed, format!("Invalid from address: {}", e)))?)
-            .to(to.parse()
-                .map_err(|e| Error::new(ErrorCode::ValidationFailed, format!("Invalid to address: {}", e)))?)
-            .subject(subject);
-
-        // Add both HTML and text bodies for best compatibility
-        let body = if let Some(text) = text_body {
-            lettre::message::MultiPart::alternative()
-                .singlepart(
-                    lettre::message::SinglePart::builder()
-                        .header(ContentType::TEXT_PLAIN)
-                        .body(text.to_string())
-                )
-                .singlepart(
-                    lettre::message::SinglePart::builder()
-                        .header(ContentType::TEXT_HTML)
-                        .body(html_body.to_string())
-                )
-        } else {
-            lettre::message::MultiPart::alternative()
-                .singlepart(
-                    lettre::message::SinglePart::builder()
-                        .header(ContentType::TEXT_HTML)
-                        .body(html_body.to_string())
-                )
-        };
-
-        let message = message_builder
-            .multipart(body)
-            .map_err(|e| Error::new(ErrorCode::ValidationFailed, format!("Failed to build email: {}", e)))?;
+        let message = self.build_message(to, subject, html_body, text_body)?;
 
-        // Send email
-        for attempt in 1..=self.config.max_retries {
+        // Transient failures are retried here a few times; what is still failing
+        // goes back to the job executor with a code that tells it whether to retry
+        let mut attempt = 1;
+        loop {
             match transport.send(message.clone()).await {
                 Ok(_) => {
                     info!(
@@ -257,7 +315,8 @@ impl EmailService {
                     return Ok(());
                 }
                 Err(e) => {
-                    if attempt < self.config.max_retries {
+                    let code = smtp_error_code(&e);
+                    if attempt < self.config.max_retries && code.is_retryable() {
                         warn!(
                             to = to,
                             attempt = attempt,
@@ -265,6 +324,7 @@ impl EmailService {
                             "Email send failed, retrying"
                         );
                         tokio::time::sleep(Duration::from_secs(attempt as u64)).await;
+                        attempt += 1;
                     } else {
                         error!(
                             to = to,
@@ -273,15 +333,92 @@ impl EmailService {
                             "Email send failed permanently"
                         );
                         return Err(Error::new(
-                            ErrorCode::ExternalServiceError,
+                            code,
                             format!("Failed to send email after {} attempts: {}", attempt, e)
                         ));
                     }
                 }
             }
         }
+    }
+
+    /// Build the message with the configured sender and reply-to address
+    fn build_message(
+        &self,
+        to: &str,
+        subject: &str,
+        html_body: &str,
+        text_body: Option<&str>,
+    ) -> Result<Message> {
+        let mut message_builder = Message::builder()
+            .from(format!("{} <{}>", self.config.smtp_from_name, self.config.smtp_from_email).parse()
+                .map_err(|e| Error::new(ErrorCode::ConfigurationError, format!("Invalid from address: {}", e)))?)
+            .to(to.parse()
+                .map_err(|e| Error::new(ErrorCode::ValidationFailed, format!("Invalid to address: {}", e)))?)
+            .subject(subject);
+
+        if let Some(reply_to) = &self.config.smtp_reply_to {
+            message_builder = message_builder.reply_to(reply_to.parse()
+                .map_err(|e| Error::new(ErrorCode::ConfigurationError, format!("Invalid reply-to address: {}", e)))?);
+        }
 
-        unreachable!()
+        // Add both HTML and text bodies for best compatibility
+        let body = if let Some(text) = text_body {
+            lettre::message::MultiPart::alternative()
+                .singlepart(
+                    lettre::message::SinglePart::builder()
+                        .header(ContentType::TEXT_PLAIN)
+                        .body(text.to_string())
+                )
+                .singlepart(
+                    lettre::message::SinglePart::builder()
+                        .header(ContentType::TEXT_HTML)
+                        .body(html_body.to_string())
+                )
+        } else {
+            lettre::message::MultiPart::alternative()
+                .singlepart(
+                    lettre::message::SinglePart::builder()
+                        .header(ContentType::TEXT_HTML)
+                        .body(html_body.to_string())
+                )
+        };
+
+        message_builder
+            .multipart(body)
+            .map_err(|e| Error::new(ErrorCode::ValidationFailed, format!("Failed to build email: {}", e)))
+    }
+
+    /// Record the message in the audit log instead of sending it (`log` provider)
+    async fn record_email(
+        &self,
+        to: &str,
+        subject: &str,
+        html_body: &str,
+        text_body: Option<&str>,
+    ) -> Result<()> {
+        // Built anyway, so that a bad sender or reply-to address shows up before production
+        self.build_message(to, subject, html_body, text_body)?;
+
+        info!(to = to, subject = subject, "Email recorded instead of sent (log provider)");
+        let Some(audit_logger) = &self.audit_logger else {
+            return Ok(());
+        };
+
+        let mut event = AuditEvent::builder(EventType::Custom("EMAIL_RECORDED".to_string()), "Email recorded instead of sent")
+            .severity(EventSeverity::Info)
+            .outcome(EventOutcome::Success)
+            .metadata("recipient".to_string(), serde_json::Value::String(to.to_string()))
+            .metadata("subject".to_string(), serde_json::Value::String(subject.to_string()))
+            .metadata("from".to_string(), serde_json::Value::String(self.config.smtp_from_email.clone()))
+            .metadata("html_body".to_string(), serde_json::Value::String(html_body.to_string()));
+        if let Some(text) = text_body {
+            event = event.metadata("text_body".to_string(), serde_json::Value::String(text.to_string()));
+        }
+        if let Some(reply_to) = &self.config.smtp_reply_to {
+            event = event.metadata("reply_to".to_string(), serde_json::Value::String(reply_to.clone()));
+        }
+        audit_logger.log_event(event.build()).await
     }
 
     /// Create SMTP transport for regular SMTP servers
@@ -293,14 +430,9 @@ impl EmailService {
 
         let mut transport_builder = AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(smtp_host)
             .port(smtp_port)
-            .timeout(Some(Duration::from_secs(config.timeout_seconds)));
-
-        // Configure TLS - use None for now to avoid TlsParameters issues
-        if config.use_tls {
-            transport_builder = transport_builder.tls(Tls::None);
-        } else if config.use_starttls {
-            transport_builder = transport_builder.tls(Tls::None);
-        }
+            .tls(Self::smtp_tls(config, smtp_host)?)
+            .timeout(Some(Duration::from_secs(config.timeout_seconds)))
+            .pool_config(Self::smtp_pool_config(&config.smtp_pool));
 
         // Configure authentication if credentials are provided
         if let (Some(username), Some(password)) = (&config.smtp_username, &config.smtp_password) {
@@ -316,6 +448,27 @@ impl EmailService {
         Ok(transport)
     }
 
+    /// STARTTLS when `use_starttls` is set, TLS from the first byte when only `use_tls` is
+    fn smtp_tls(config: &EmailConfig, smtp_host: &str) -> Result<Tls> {
+        if !config.use_tls && !config.use_starttls {
+            return Ok(Tls::None);
+        }
+
+        let parameters = TlsParameters::new(smtp_host.to_string())
+            .map_err(|e| Error::new(ErrorCode::ConfigurationError, format!("Invalid TLS parameters for {}: {}", smtp_host, e)))?;
+        Ok(if config.use_starttls {
+            Tls::Required(parameters)
+        } else {
+            Tls::Wrapper(parameters)
+        })
+    }
+
+    fn smtp_pool_config(pool: &SmtpPoolConfig) -> PoolConfig {
+        PoolConfig::new()
+            .max_size(pool.max_size.max(1))
+            .idle_timeout(Duration::from_secs(pool.idle_timeout_seconds))
+    }
+
     /// Create SendGrid SMTP transport
     fn create_sendgrid_transport(config: &EmailConfig) -> Result<AsyncSmtpTransport<Tokio1Executor>> {
         let api_key = config.sendgrid_api_key.as_ref()
@@ -326,6 +479,7 @@ impl EmailService {
             .port(587)
             .credentials(Credentials::new("apikey".to_string(), api_key.clone()))
             .timeout(Some(Duration::from_secs(config.timeout_seconds)))
+            .pool_config(Self::smtp_pool_config(&config.smtp_pool))
             .build();
 
         Ok(transport)
@@ -346,6 +500,7 @@ impl EmailService {
             .port(587)
             .credentials(Credentials::new(access_key.clone(), secret_key.clone()))
             .timeout(Some(Duration::from_secs(config.timeout_seconds)))
+            .pool_config(Self::smtp_pool_config(&config.smtp_pool))
             .build();
 
         Ok(transport)
@@ -396,6 +551,33 @@ impl EmailService {
     }
 }
 
+/// Error code for a failed SMTP exchange.
+///
+/// Timeouts, 4xx replies and connection failures may pass on a later attempt
+/// and get codes the job executor retries. Rejected credentials and TLS
+/// failures need a configuration change, other 5xx replies a different
+/// message or recipient; those are not retried.
+fn smtp_error_code(error: &smtp::Error) -> ErrorCode {
+    if error.is_timeout() {
+        ErrorCode::NetworkTimeout
+    } else if error.is_transient() {
+        ErrorCode::ServiceUnavailable
+    } else if error.is_permanent() {
+        let status = error.status().map(|code| code.to_string());
+        match status.as_deref() {
+            // Authentication required, too weak or rejected
+            Some("530" | "534" | "535") => ErrorCode::ConfigurationError,
+            _ => ErrorCode::InvalidInput,
+        }
+    } else if error.is_tls() {
+        ErrorCode::ConfigurationError
+    } else if error.is_client() {
+        ErrorCode::ValidationFailed
+    } else {
+        ErrorCode::NetworkConnectionRefused
+    }
+}
+
 /// Result of bulk email operation
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BulkEmailResult {
@@ -480,6 +662,130 @@ mod tests {
         assert!(bulk_result.has_failures());
     }
 
+    #[derive(Default)]
+    struct RecordingObserver {
+        sent: std::sync::Mutex<Vec<SentEmail>>,
+    }
+
+    #[async_trait::async_trait]
+    impl SentEmailObserver for RecordingObserver {
+        async fn email_sent(&self, email: &SentEmail) {
+            self.sent.lock().unwrap().push(email.clone());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_observers_see_referenced_emails_only() {
+        let observer = Arc::new(RecordingObserver::default());
+        let service = EmailService::mock().with_observer(observer.clone());
+        let reference = EmailReference::customer(uuid::Uuid::new_v4(), uuid::Uuid::new_v4());
+
+        service
+            .send_email_with_reference("buyer@example.com", "Your quote", "<p>Quote</p>", Some("Quote"), Some(&reference))
+            .await
+            .unwrap();
+        service.send_email("other@example.com", "No reference", "<p>Hi</p>", None).await.unwrap();
+        assert!(service
+            .send_email_with_reference("fail@example.com", "Bounced", "<p>Quote</p>", None, Some(&reference))
+            .await
+            .is_err());
+
+        let sent = observer.sent.lock().unwrap();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].to, "buyer@example.com");
+        assert_eq!(sent[0].reference, reference);
+        assert_eq!(sent[0].text_body.as_deref(), Some("Quote"));
+    }
+
+    fn smtp_config(use_tls: bool, use_starttls: bool) -> EmailConfig {
+        EmailConfig {
+            provider: "smtp".to_string(),
+            smtp_host: Some("smtp.example.com".to_string()),
+            use_tls,
+            use_starttls,
+            ..EmailConfig::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_smtp_tls_follows_config() {
+        let tls = |config: EmailConfig| EmailService::smtp_tls(&config, "smtp.example.com").unwrap();
+
+        assert!(matches!(tls(smtp_config(true, true)), Tls::Required(_)));
+        assert!(matches!(tls(smtp_config(false, true)), Tls::Required(_)));
+        assert!(matches!(tls(smtp_config(true, false)), Tls::Wrapper(_)));
+        assert!(matches!(tls(smtp_config(false, false)), Tls::None));
+        assert!(EmailService::new(smtp_config(true, true)).is_ok());
+    }
+
+    #[derive(Default)]
+    struct MemoryAuditBackend {
+        events: std::sync::Mutex<Vec<AuditEvent>>,
+    }
+
+    #[async_trait::async_trait]
+    impl erp_core::audit::AuditBackend for MemoryAuditBackend {
+        async fn store_event(&self, event: &AuditEvent) -> Result<()> {
+            self.events.lock().unwrap().push(event.clone());
+            Ok(())
+        }
+
+        async fn query(
+            &self,
+            _filter: &erp_core::audit::AuditFilter,
+            pagination: erp_core::audit::PaginationOptions,
+        ) -> Result<erp_core::audit::PaginationResult<AuditEvent>> {
+            Ok(erp_core::audit::PaginationResult::new(Vec::new(), 0, pagination))
+        }
+
+        async fn count_events(&self, _filter: &erp_core::audit::AuditFilter) -> Result<u64> {
+            Ok(self.events.lock().unwrap().len() as u64)
+        }
+
+        async fn health_check(&self) -> Result<erp_core::audit::traits::BackendHealth> {
+            Ok(erp_core::audit::traits::BackendHealth { is_healthy: true, message: None, last_write: None, events_stored_today: None })
+        }
+
+        async fn cleanup_old_events(&self, _older_than: chrono::DateTime<chrono::Utc>) -> Result<u64> {
+            Ok(0)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_log_provider_records_emails_in_audit_log() {
+        let backend = Arc::new(MemoryAuditBackend::default());
+        let audit_logger = AuditLogger::new(backend.clone(), Arc::new(erp_core::error::ErrorMetrics::new()));
+        let config = EmailConfig {
+            provider: "log".to_string(),
+            smtp_reply_to: Some("support@example.com".to_string()),
+            ..EmailConfig::default()
+        };
+        let service = EmailService::new(config).unwrap().with_audit_logger(audit_logger);
+
+        service.send_email("buyer@example.com", "Your quote", "<p>Quote</p>", Some("Quote")).await.unwrap();
+
+        let events = backend.events.lock().unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event_type, EventType::Custom("EMAIL_RECORDED".to_string()));
+        assert_eq!(events[0].metadata["recipient"], "buyer@example.com");
+        assert_eq!(events[0].metadata["reply_to"], "support@example.com");
+        assert_eq!(events[0].metadata["text_body"], "Quote");
+    }
+
+    #[tokio::test]
+    async fn test_invalid_reply_to_is_a_configuration_error() {
+        let config = EmailConfig {
+            provider: "log".to_string(),
+            smtp_reply_to: Some("not an address".to_string()),
+            ..EmailConfig::default()
+        };
+        let service = EmailService::new(config).unwrap();
+
+        let error = service.send_email("buyer@example.com", "Your quote", "<p>Quote</p>", None).await.unwrap_err();
+
+        assert_eq!(error.code, ErrorCode::ConfigurationError);
+    }
+
     #[test]
     fn test_email_config_default() {
         let config = EmailConfig::default();
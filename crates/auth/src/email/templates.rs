@@ -1,37 +1,349 @@
+//! Built-in email templates
+//!
+//! Every email the auth service sends is a named template in
+//! [`BUILT_IN_TEMPLATES`], written for the sandboxed engine in
+//! [`super::engine`] and declaring the variables it is rendered with. The
+//! template structs below fill those variables; tenants may replace the
+//! wording with overrides validated against the same declarations (see
+//! [`super::overrides`]).
+
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use super::engine::{Output, Template, TemplateSyntaxError, TemplateValues};
 
 /// Base trait for email templates
 pub trait EmailTemplate: Send + Sync {
+    /// Get template name for logging/debugging
+    fn template_name(&self) -> &'static str;
+
+    /// Name of the template in the registry; one template may serve several
+    /// emails logged under their own names
+    fn registered_name(&self) -> &'static str {
+        self.template_name()
+    }
+
+    /// Values of the variables the registered template declares
+    fn variables(&self) -> TemplateValues;
+
     /// Get the email subject
-    fn subject(&self) -> String;
-    
+    fn subject(&self) -> String {
+        render_built_in(self.registered_name(), TemplatePart::Subject, &self.variables())
+    }
+
     /// Get the HTML body
-    fn html_body(&self) -> String;
-    
+    fn html_body(&self) -> String {
+        render_built_in(self.registered_name(), TemplatePart::HtmlBody, &self.variables())
+    }
+
     /// Get the text body (fallback)
-    fn text_body(&self) -> String;
-    
-    /// Get template name for logging/debugging
-    fn template_name(&self) -> &'static str;
+    fn text_body(&self) -> String {
+        render_built_in(self.registered_name(), TemplatePart::TextBody, &self.variables())
+    }
 }
 
-/// Email verification template
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct VerificationEmailTemplate {
-    pub user_name: String,
-    pub company_name: String,
-    pub verification_url: String,
-    pub expires_in_hours: u32,
+/// Part of an email a template source renders
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TemplatePart {
+    Subject,
+    HtmlBody,
+    TextBody,
 }
 
-impl EmailTemplate for VerificationEmailTemplate {
-    fn subject(&self) -> String {
-        format!("Verify your account for {}", self.company_name)
+impl TemplatePart {
+    pub const ALL: [TemplatePart; 3] = [TemplatePart::Subject, TemplatePart::HtmlBody, TemplatePart::TextBody];
+
+    /// How values are written into this part
+    pub fn output(self) -> Output {
+        match self {
+            TemplatePart::Subject => Output::Header,
+            TemplatePart::HtmlBody => Output::Html,
+            TemplatePart::TextBody => Output::Text,
+        }
     }
+}
 
-    fn html_body(&self) -> String {
-        format!(
-            r#"
+/// Type of a template variable, as the values are given
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VariableType {
+    Text,
+    Url,
+    Integer,
+    Boolean,
+    /// Already formatted, like `2025-03-01 14:30 UTC`
+    DateTime,
+}
+
+/// A variable a template may use
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct TemplateVariable {
+    pub name: &'static str,
+    #[serde(rename = "type")]
+    pub kind: VariableType,
+    /// Overrides must output it in both bodies
+    pub required: bool,
+    pub description: &'static str,
+    /// Value used for previews
+    pub sample: &'static str,
+}
+
+impl TemplateVariable {
+    fn sample_value(&self) -> Value {
+        match self.kind {
+            VariableType::Integer => self.sample.parse::<i64>().map(Value::from).unwrap_or(Value::Null),
+            VariableType::Boolean => Value::Bool(self.sample == "true"),
+            _ => Value::String(self.sample.to_string()),
+        }
+    }
+}
+
+/// A named template with its declared variables and built-in wording
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct NamedTemplate {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub variables: &'static [TemplateVariable],
+    pub subject: &'static str,
+    pub html_body: &'static str,
+    pub text_body: &'static str,
+}
+
+impl NamedTemplate {
+    pub fn variable(&self, name: &str) -> Option<&TemplateVariable> {
+        self.variables.iter().find(|variable| variable.name == name)
+    }
+
+    pub fn source(&self, part: TemplatePart) -> &'static str {
+        match part {
+            TemplatePart::Subject => self.subject,
+            TemplatePart::HtmlBody => self.html_body,
+            TemplatePart::TextBody => self.text_body,
+        }
+    }
+
+    /// Sample values of every variable, for previews
+    pub fn sample_values(&self) -> TemplateValues {
+        self.variables
+            .iter()
+            .map(|variable| (variable.name.to_string(), variable.sample_value()))
+            .collect()
+    }
+}
+
+/// An email rendered from a template
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RenderedEmail {
+    pub subject: String,
+    pub html_body: String,
+    pub text_body: String,
+}
+
+impl RenderedEmail {
+    /// Render the three sources with `values`
+    pub fn render(
+        subject: &str,
+        html_body: &str,
+        text_body: &str,
+        values: &TemplateValues,
+    ) -> Result<Self, TemplateSyntaxError> {
+        let render = |source: &str, part: TemplatePart| {
+            Template::parse(source).map(|template| template.render(values, part.output()))
+        };
+        Ok(Self {
+            subject: render(subject, TemplatePart::Subject)?.trim().to_string(),
+            html_body: render(html_body, TemplatePart::HtmlBody)?,
+            text_body: render(text_body, TemplatePart::TextBody)?.trim().to_string(),
+        })
+    }
+}
+
+pub fn built_in(name: &str) -> Option<&'static NamedTemplate> {
+    BUILT_IN_TEMPLATES.iter().find(|template| template.name == name)
+}
+
+fn render_built_in(name: &str, part: TemplatePart, values: &TemplateValues) -> String {
+    let template = built_in(name).unwrap_or_else(|| panic!("no built-in email template `{}`", name));
+    let rendered = Template::parse(template.source(part))
+        .unwrap_or_else(|e| panic!("built-in email template `{}` does not parse: {}", name, e))
+        .render(values, part.output());
+    match part {
+        TemplatePart::HtmlBody => rendered,
+        _ => rendered.trim().to_string(),
+    }
+}
+
+fn values(value: Value) -> TemplateValues {
+    match value {
+        Value::Object(values) => values,
+        _ => TemplateValues::new(),
+    }
+}
+
+const USER_NAME: TemplateVariable = TemplateVariable {
+    name: "user_name",
+    kind: VariableType::Text,
+    required: false,
+    description: "Full name of the recipient",
+    sample: "Jane Smith",
+};
+
+const COMPANY_NAME: TemplateVariable = TemplateVariable {
+    name: "company_name",
+    kind: VariableType::Text,
+    required: false,
+    description: "Name of the company or tenant the account belongs to",
+    sample: "Acme Corp",
+};
+
+const SOURCE_IP: TemplateVariable = TemplateVariable {
+    name: "source_ip",
+    kind: VariableType::Text,
+    required: false,
+    description: "IP address the request came from; may be empty",
+    sample: "203.0.113.7",
+};
+
+/// Every template the auth service sends
+pub const BUILT_IN_TEMPLATES: [NamedTemplate; 5] = [
+    NamedTemplate {
+        name: "email_verification",
+        description: "Asks a newly registered user to verify their email address",
+        variables: &[
+            USER_NAME,
+            COMPANY_NAME,
+            TemplateVariable {
+                name: "verification_url",
+                kind: VariableType::Url,
+                required: true,
+                description: "Link that verifies the address",
+                sample: "https://erp.example.com/auth/verify-email?token=sample",
+            },
+            TemplateVariable {
+                name: "expires_in_hours",
+                kind: VariableType::Integer,
+                required: false,
+                description: "Hours until the link expires",
+                sample: "24",
+            },
+        ],
+        subject: "Verify your account for {{company_name}}",
+        html_body: VERIFICATION_HTML,
+        text_body: VERIFICATION_TEXT,
+    },
+    NamedTemplate {
+        name: "password_reset",
+        description: "Link to reset a forgotten password",
+        variables: &[
+            USER_NAME,
+            COMPANY_NAME,
+            TemplateVariable {
+                name: "reset_url",
+                kind: VariableType::Url,
+                required: true,
+                description: "Link to choose a new password",
+                sample: "https://erp.example.com/auth/reset-password?token=sample",
+            },
+            TemplateVariable {
+                name: "expires_in_hours",
+                kind: VariableType::Integer,
+                required: false,
+                description: "Hours until the link expires",
+                sample: "1",
+            },
+            SOURCE_IP,
+        ],
+        subject: "Password reset request for {{company_name}}",
+        html_body: PASSWORD_RESET_HTML,
+        text_body: PASSWORD_RESET_TEXT,
+    },
+    NamedTemplate {
+        name: "welcome",
+        description: "Sent once the email address is verified",
+        variables: &[
+            USER_NAME,
+            COMPANY_NAME,
+            TemplateVariable {
+                name: "login_url",
+                kind: VariableType::Url,
+                required: true,
+                description: "Sign-in page",
+                sample: "https://erp.example.com/auth/login",
+            },
+        ],
+        subject: "Welcome to {{company_name}}! Your account is now active",
+        html_body: WELCOME_HTML,
+        text_body: WELCOME_TEXT,
+    },
+    NamedTemplate {
+        name: "password_changed",
+        description: "Tells a user their password was changed",
+        variables: &[
+            USER_NAME,
+            COMPANY_NAME,
+            TemplateVariable {
+                name: "support_url",
+                kind: VariableType::Url,
+                required: true,
+                description: "Where to report a change the user did not make",
+                sample: "https://erp.example.com/support",
+            },
+            TemplateVariable {
+                name: "changed_at",
+                kind: VariableType::DateTime,
+                required: false,
+                description: "When the password was changed",
+                sample: "2025-03-01 14:30 UTC",
+            },
+            SOURCE_IP,
+        ],
+        subject: "Your {{company_name}} password was changed",
+        html_body: PASSWORD_CHANGED_HTML,
+        text_body: PASSWORD_CHANGED_TEXT,
+    },
+    NamedTemplate {
+        name: "invitation",
+        description: "Invitation to join a tenant, and the reminder while it is open",
+        variables: &[
+            USER_NAME,
+            TemplateVariable {
+                name: "inviter_name",
+                kind: VariableType::Text,
+                required: false,
+                description: "Who sent the invitation",
+                sample: "John Doe",
+            },
+            COMPANY_NAME,
+            TemplateVariable {
+                name: "accept_url",
+                kind: VariableType::Url,
+                required: true,
+                description: "Link to accept the invitation and choose a password",
+                sample: "https://erp.example.com/auth/invitations/sample",
+            },
+            TemplateVariable {
+                name: "expires_at",
+                kind: VariableType::DateTime,
+                required: false,
+                description: "When the invitation expires",
+                sample: "2025-03-08 09:00 UTC",
+            },
+            TemplateVariable {
+                name: "reminder",
+                kind: VariableType::Boolean,
+                required: false,
+                description: "Whether this is the reminder sent halfway to expiry",
+                sample: "false",
+            },
+        ],
+        subject: "{{#if reminder}}Reminder: your invitation to {{company_name}} is waiting{{else}}{{inviter_name}} invited you to {{company_name}}{{/if}}",
+        html_body: INVITATION_HTML,
+        text_body: INVITATION_TEXT,
+    },
+];
+
+const VERIFICATION_HTML: &str = r#"
 <!DOCTYPE html>
 <html lang="en">
 <head>
@@ -39,11 +351,11 @@ impl EmailTemplate for VerificationEmailTemplate {
     <meta name="viewport" content="width=device-width, initial-scale=1.0">
     <title>Email Verification</title>
     <style>
-        body {{ font-family: Arial, sans-serif; line-height: 1.6; color: #333; }}
-        .container {{ max-width: 600px; margin: 0 auto; padding: 20px; }}
-        .header {{ background-color: #2563eb; color: white; padding: 20px; text-align: center; }}
-        .content {{ padding: 20px; background-color: #f8fafc; }}
-        .button {{ 
+        body { font-family: Arial, sans-serif; line-height: 1.6; color: #333; }
+        .container { max-width: 600px; margin: 0 auto; padding: 20px; }
+        .header { background-color: #2563eb; color: white; padding: 20px; text-align: center; }
+        .content { padding: 20px; background-color: #f8fafc; }
+        .button { 
             display: inline-block; 
             background-color: #2563eb; 
             color: white; 
@@ -51,106 +363,59 @@ impl EmailTemplate for VerificationEmailTemplate {
             text-decoration: none; 
             border-radius: 6px; 
             margin: 20px 0; 
-        }}
-        .footer {{ padding: 20px; text-align: center; color: #6b7280; font-size: 14px; }}
-        .warning {{ color: #dc2626; font-weight: bold; }}
+        }
+        .footer { padding: 20px; text-align: center; color: #6b7280; font-size: 14px; }
+        .warning { color: #dc2626; font-weight: bold; }
     </style>
 </head>
 <body>
     <div class="container">
         <div class="header">
-            <h1>Welcome to {}</h1>
+            <h1>Welcome to {{company_name}}</h1>
         </div>
         <div class="content">
-            <h2>Hi {},</h2>
-            <p>Thank you for registering with {}! To complete your account setup, please verify your email address by clicking the button below.</p>
+            <h2>Hi {{user_name}},</h2>
+            <p>Thank you for registering with {{company_name}}! To complete your account setup, please verify your email address by clicking the button below.</p>
             
             <div style="text-align: center;">
-                <a href="{}" class="button">Verify Email Address</a>
+                <a href="{{verification_url}}" class="button">Verify Email Address</a>
             </div>
             
-            <p><strong>This verification link will expire in {} hours.</strong></p>
+            <p><strong>This verification link will expire in {{expires_in_hours}} hours.</strong></p>
             
             <p>If you didn't create an account with us, you can safely ignore this email.</p>
             
             <p>If you're unable to click the button above, copy and paste the following link into your browser:</p>
-            <p style="word-break: break-all; color: #2563eb;">{}</p>
+            <p style="word-break: break-all; color: #2563eb;">{{verification_url}}</p>
         </div>
         <div class="footer">
             <p>This is an automated email. Please do not reply to this message.</p>
-            <p>&copy; {} ERP System. All rights reserved.</p>
+            <p>&copy; {{company_name}} ERP System. All rights reserved.</p>
         </div>
     </div>
 </body>
 </html>
-            "#,
-            self.company_name,
-            self.user_name,
-            self.company_name,
-            self.verification_url,
-            self.expires_in_hours,
-            self.verification_url,
-            self.company_name
-        )
-    }
+"#;
 
-    fn text_body(&self) -> String {
-        format!(
-            r#"
-Welcome to {}!
+const VERIFICATION_TEXT: &str = r#"
+Welcome to {{company_name}}!
 
-Hi {},
+Hi {{user_name}},
 
-Thank you for registering with {}! To complete your account setup, please verify your email address by visiting the following link:
+Thank you for registering with {{company_name}}! To complete your account setup, please verify your email address by visiting the following link:
 
-{}
+{{verification_url}}
 
-This verification link will expire in {} hours.
+This verification link will expire in {{expires_in_hours}} hours.
 
 If you didn't create an account with us, you can safely ignore this email.
 
 ---
 This is an automated email. Please do not reply to this message.
-© {} ERP System. All rights reserved.
-            "#,
-            self.company_name,
-            self.user_name,
-            self.company_name,
-            self.verification_url,
-            self.expires_in_hours,
-            self.company_name
-        ).trim().to_string()
-    }
-
-    fn template_name(&self) -> &'static str {
-        "email_verification"
-    }
-}
-
-/// Password reset email template
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct PasswordResetEmailTemplate {
-    pub user_name: String,
-    pub company_name: String,
-    pub reset_url: String,
-    pub expires_in_hours: u32,
-    pub source_ip: Option<String>,
-}
-
-impl EmailTemplate for PasswordResetEmailTemplate {
-    fn subject(&self) -> String {
-        format!("Password reset request for {}", self.company_name)
-    }
+© {{company_name}} ERP System. All rights reserved.
+"#;
 
-    fn html_body(&self) -> String {
-        let ip_info = if let Some(ip) = &self.source_ip {
-            format!("<p><strong>Request origin:</strong> {}</p>", ip)
-        } else {
-            String::new()
-        };
-
-        format!(
-            r#"
+const PASSWORD_RESET_HTML: &str = r#"
 <!DOCTYPE html>
 <html lang="en">
 <head>
@@ -158,11 +423,11 @@ impl EmailTemplate for PasswordResetEmailTemplate {
     <meta name="viewport" content="width=device-width, initial-scale=1.0">
     <title>Password Reset</title>
     <style>
-        body {{ font-family: Arial, sans-serif; line-height: 1.6; color: #333; }}
-        .container {{ max-width: 600px; margin: 0 auto; padding: 20px; }}
-        .header {{ background-color: #dc2626; color: white; padding: 20px; text-align: center; }}
-        .content {{ padding: 20px; background-color: #f8fafc; }}
-        .button {{ 
+        body { font-family: Arial, sans-serif; line-height: 1.6; color: #333; }
+        .container { max-width: 600px; margin: 0 auto; padding: 20px; }
+        .header { background-color: #dc2626; color: white; padding: 20px; text-align: center; }
+        .content { padding: 20px; background-color: #f8fafc; }
+        .button { 
             display: inline-block; 
             background-color: #dc2626; 
             color: white; 
@@ -170,10 +435,10 @@ impl EmailTemplate for PasswordResetEmailTemplate {
             text-decoration: none; 
             border-radius: 6px; 
             margin: 20px 0; 
-        }}
-        .footer {{ padding: 20px; text-align: center; color: #6b7280; font-size: 14px; }}
-        .warning {{ color: #dc2626; font-weight: bold; }}
-        .security-info {{ background-color: #fef2f2; border: 1px solid #fecaca; padding: 15px; margin: 15px 0; border-radius: 6px; }}
+        }
+        .footer { padding: 20px; text-align: center; color: #6b7280; font-size: 14px; }
+        .warning { color: #dc2626; font-weight: bold; }
+        .security-info { background-color: #fef2f2; border: 1px solid #fecaca; padding: 15px; margin: 15px 0; border-radius: 6px; }
     </style>
 </head>
 <body>
@@ -182,108 +447,64 @@ impl EmailTemplate for PasswordResetEmailTemplate {
             <h1>Password Reset Request</h1>
         </div>
         <div class="content">
-            <h2>Hi {},</h2>
-            <p>We received a request to reset your password for your {} account.</p>
+            <h2>Hi {{user_name}},</h2>
+            <p>We received a request to reset your password for your {{company_name}} account.</p>
             
             <div class="security-info">
                 <p><strong>Security Information:</strong></p>
-                {}
+                {{#if source_ip}}<p><strong>Request origin:</strong> {{source_ip}}</p>{{/if}}
                 <p><strong>Request time:</strong> Just now</p>
                 <p class="warning">If you didn't request this password reset, please contact support immediately.</p>
             </div>
             
             <div style="text-align: center;">
-                <a href="{}" class="button">Reset Password</a>
+                <a href="{{reset_url}}" class="button">Reset Password</a>
             </div>
             
-            <p><strong>This password reset link will expire in {} hours.</strong></p>
+            <p><strong>This password reset link will expire in {{expires_in_hours}} hours.</strong></p>
             
             <p>If you didn't request a password reset, you can safely ignore this email. Your password will not be changed.</p>
             
             <p>If you're unable to click the button above, copy and paste the following link into your browser:</p>
-            <p style="word-break: break-all; color: #dc2626;">{}</p>
+            <p style="word-break: break-all; color: #dc2626;">{{reset_url}}</p>
         </div>
         <div class="footer">
             <p>This is an automated email. Please do not reply to this message.</p>
             <p>If you need help, contact our support team.</p>
-            <p>&copy; {} ERP System. All rights reserved.</p>
+            <p>&copy; {{company_name}} ERP System. All rights reserved.</p>
         </div>
     </div>
 </body>
 </html>
-            "#,
-            self.user_name,
-            self.company_name,
-            ip_info,
-            self.reset_url,
-            self.expires_in_hours,
-            self.reset_url,
-            self.company_name
-        )
-    }
-
-    fn text_body(&self) -> String {
-        let ip_info = if let Some(ip) = &self.source_ip {
-            format!("Request origin: {}\n", ip)
-        } else {
-            String::new()
-        };
+"#;
 
-        format!(
-            r#"
+const PASSWORD_RESET_TEXT: &str = r#"
 Password Reset Request
 
-Hi {},
+Hi {{user_name}},
 
-We received a request to reset your password for your {} account.
+We received a request to reset your password for your {{company_name}} account.
 
 Security Information:
-{}Request time: Just now
+{{#if source_ip}}Request origin: {{source_ip}}
+{{/if}}Request time: Just now
 
 WARNING: If you didn't request this password reset, please contact support immediately.
 
 To reset your password, visit the following link:
-{}
+{{reset_url}}
 
-This password reset link will expire in {} hours.
+This password reset link will expire in {{expires_in_hours}} hours.
 
 If you didn't request a password reset, you can safely ignore this email. Your password will not be changed.
 
 ---
 This is an automated email. Please do not reply to this message.
 If you need help, contact our support team.
-© {} ERP System. All rights reserved.
-            "#,
-            self.user_name,
-            self.company_name,
-            ip_info,
-            self.reset_url,
-            self.expires_in_hours,
-            self.company_name
-        ).trim().to_string()
-    }
-
-    fn template_name(&self) -> &'static str {
-        "password_reset"
-    }
-}
-
-/// Welcome email template (after successful verification)
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct WelcomeEmailTemplate {
-    pub user_name: String,
-    pub company_name: String,
-    pub login_url: String,
-}
+© {{company_name}} ERP System. All rights reserved.
+"#;
 
-impl EmailTemplate for WelcomeEmailTemplate {
-    fn subject(&self) -> String {
-        format!("Welcome to {}! Your account is now active", self.company_name)
-    }
-
-    fn html_body(&self) -> String {
-        format!(
-            r#"
+const WELCOME_HTML: &str = r#"
 <!DOCTYPE html>
 <html lang="en">
 <head>
@@ -291,11 +512,11 @@ impl EmailTemplate for WelcomeEmailTemplate {
     <meta name="viewport" content="width=device-width, initial-scale=1.0">
     <title>Welcome</title>
     <style>
-        body {{ font-family: Arial, sans-serif; line-height: 1.6; color: #333; }}
-        .container {{ max-width: 600px; margin: 0 auto; padding: 20px; }}
-        .header {{ background-color: #16a34a; color: white; padding: 20px; text-align: center; }}
-        .content {{ padding: 20px; background-color: #f8fafc; }}
-        .button {{ 
+        body { font-family: Arial, sans-serif; line-height: 1.6; color: #333; }
+        .container { max-width: 600px; margin: 0 auto; padding: 20px; }
+        .header { background-color: #16a34a; color: white; padding: 20px; text-align: center; }
+        .content { padding: 20px; background-color: #f8fafc; }
+        .button { 
             display: inline-block; 
             background-color: #16a34a; 
             color: white; 
@@ -303,18 +524,18 @@ impl EmailTemplate for WelcomeEmailTemplate {
             text-decoration: none; 
             border-radius: 6px; 
             margin: 20px 0; 
-        }}
-        .footer {{ padding: 20px; text-align: center; color: #6b7280; font-size: 14px; }}
-        .features {{ background-color: #f0fdf4; border: 1px solid #bbf7d0; padding: 15px; margin: 15px 0; border-radius: 6px; }}
+        }
+        .footer { padding: 20px; text-align: center; color: #6b7280; font-size: 14px; }
+        .features { background-color: #f0fdf4; border: 1px solid #bbf7d0; padding: 15px; margin: 15px 0; border-radius: 6px; }
     </style>
 </head>
 <body>
     <div class="container">
         <div class="header">
-            <h1>🎉 Welcome to {}!</h1>
+            <h1>🎉 Welcome to {{company_name}}!</h1>
         </div>
         <div class="content">
-            <h2>Hi {},</h2>
+            <h2>Hi {{user_name}},</h2>
             <p>Congratulations! Your email has been verified and your account is now active.</p>
             
             <div class="features">
@@ -328,32 +549,24 @@ impl EmailTemplate for WelcomeEmailTemplate {
             </div>
             
             <div style="text-align: center;">
-                <a href="{}" class="button">Access Your Account</a>
+                <a href="{{login_url}}" class="button">Access Your Account</a>
             </div>
             
             <p>If you have any questions or need assistance getting started, our support team is here to help.</p>
         </div>
         <div class="footer">
             <p>Thank you for choosing our ERP system!</p>
-            <p>&copy; {} ERP System. All rights reserved.</p>
+            <p>&copy; {{company_name}} ERP System. All rights reserved.</p>
         </div>
     </div>
 </body>
 </html>
-            "#,
-            self.company_name,
-            self.user_name,
-            self.login_url,
-            self.company_name
-        )
-    }
+"#;
 
-    fn text_body(&self) -> String {
-        format!(
-            r#"
-🎉 Welcome to {}!
+const WELCOME_TEXT: &str = r#"
+🎉 Welcome to {{company_name}}!
 
-Hi {},
+Hi {{user_name}},
 
 Congratulations! Your email has been verified and your account is now active.
 
@@ -363,23 +576,299 @@ What you can do now:
 - Configure system settings
 - Start using all ERP features
 
-Access your account: {}
+Access your account: {{login_url}}
 
 If you have any questions or need assistance getting started, our support team is here to help.
 
 Thank you for choosing our ERP system!
-© {} ERP System. All rights reserved.
-            "#,
-            self.company_name,
-            self.user_name,
-            self.login_url,
-            self.company_name
-        ).trim().to_string()
+© {{company_name}} ERP System. All rights reserved.
+"#;
+
+const PASSWORD_CHANGED_HTML: &str = r#"
+<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>Password Changed</title>
+    <style>
+        body { font-family: Arial, sans-serif; line-height: 1.6; color: #333; }
+        .container { max-width: 600px; margin: 0 auto; padding: 20px; }
+        .header { background-color: #2563eb; color: white; padding: 20px; text-align: center; }
+        .content { padding: 20px; background-color: #f8fafc; }
+        .button {
+            display: inline-block;
+            background-color: #dc2626;
+            color: white;
+            padding: 12px 24px;
+            text-decoration: none;
+            border-radius: 6px;
+            margin: 20px 0;
+        }
+        .footer { padding: 20px; text-align: center; color: #6b7280; font-size: 14px; }
+        .security-info { background-color: #fef2f2; border: 1px solid #fecaca; padding: 15px; margin: 15px 0; border-radius: 6px; }
+    </style>
+</head>
+<body>
+    <div class="container">
+        <div class="header">
+            <h1>Password Changed</h1>
+        </div>
+        <div class="content">
+            <h2>Hi {{user_name}},</h2>
+            <p>The password for your {{company_name}} account was just changed. All other devices have been signed out.</p>
+
+            <div class="security-info">
+                <p><strong>Security Information:</strong></p>
+                {{#if source_ip}}<p><strong>Request origin:</strong> {{source_ip}}</p>{{/if}}
+                <p><strong>Changed at:</strong> {{changed_at}}</p>
+            </div>
+
+            <p>If you made this change, no further action is needed.</p>
+            <p>If you did not change your password, contact support right away so we can secure your account.</p>
+
+            <div style="text-align: center;">
+                <a href="{{support_url}}" class="button">Contact Support</a>
+            </div>
+        </div>
+        <div class="footer">
+            <p>This is an automated email. Please do not reply to this message.</p>
+            <p>&copy; {{company_name}} ERP System. All rights reserved.</p>
+        </div>
+    </div>
+</body>
+</html>
+"#;
+
+const PASSWORD_CHANGED_TEXT: &str = r#"
+Password Changed
+
+Hi {{user_name}},
+
+The password for your {{company_name}} account was just changed. All other devices have been signed out.
+
+Security Information:
+{{#if source_ip}}Request origin: {{source_ip}}
+{{/if}}Changed at: {{changed_at}}
+
+If you made this change, no further action is needed.
+
+If you did not change your password, contact support right away so we can secure your account:
+{{support_url}}
+
+---
+This is an automated email. Please do not reply to this message.
+© {{company_name}} ERP System. All rights reserved.
+"#;
+
+const INVITATION_HTML: &str = r#"
+<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>Invitation</title>
+    <style>
+        body { font-family: Arial, sans-serif; line-height: 1.6; color: #333; }
+        .container { max-width: 600px; margin: 0 auto; padding: 20px; }
+        .header { background-color: #2563eb; color: white; padding: 20px; text-align: center; }
+        .content { padding: 20px; background-color: #f8fafc; }
+        .button {
+            display: inline-block;
+            background-color: #2563eb;
+            color: white;
+            padding: 12px 24px;
+            text-decoration: none;
+            border-radius: 6px;
+            margin: 20px 0;
+        }
+        .footer { padding: 20px; text-align: center; color: #6b7280; font-size: 14px; }
+    </style>
+</head>
+<body>
+    <div class="container">
+        <div class="header">
+            <h1>Join {{company_name}}</h1>
+        </div>
+        <div class="content">
+            <h2>Hi {{user_name}},</h2>
+            <p>{{inviter_name}} invited you to the {{company_name}} account. Accept the invitation and choose your password to sign in.</p>
+            {{#if reminder}}<p>This is a reminder: you have not accepted the invitation yet.</p>{{/if}}
+
+            <div style="text-align: center;">
+                <a href="{{accept_url}}" class="button">Accept Invitation</a>
+            </div>
+
+            <p><strong>This invitation expires on {{expires_at}}.</strong></p>
+
+            <p>If you were not expecting this invitation, you can safely ignore this email.</p>
+
+            <p>If you're unable to click the button above, copy and paste the following link into your browser:</p>
+            <p style="word-break: break-all; color: #2563eb;">{{accept_url}}</p>
+        </div>
+        <div class="footer">
+            <p>This is an automated email. Please do not reply to this message.</p>
+            <p>&copy; {{company_name}} ERP System. All rights reserved.</p>
+        </div>
+    </div>
+</body>
+</html>
+"#;
+
+const INVITATION_TEXT: &str = r#"
+Join {{company_name}}
+
+Hi {{user_name}},
+
+{{inviter_name}} invited you to the {{company_name}} account. Accept the invitation and choose your password to sign in:
+
+{{accept_url}}
+
+{{#if reminder}}This is a reminder: you have not accepted the invitation yet.
+
+{{/if}}This invitation expires on {{expires_at}}.
+
+If you were not expecting this invitation, you can safely ignore this email.
+
+---
+This is an automated email. Please do not reply to this message.
+© {{company_name}} ERP System. All rights reserved.
+"#;
+
+/// Email verification template
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerificationEmailTemplate {
+    pub user_name: String,
+    pub company_name: String,
+    pub verification_url: String,
+    pub expires_in_hours: u32,
+}
+
+impl EmailTemplate for VerificationEmailTemplate {
+    fn template_name(&self) -> &'static str {
+        "email_verification"
+    }
+
+    fn variables(&self) -> TemplateValues {
+        values(json!({
+            "user_name": self.user_name,
+            "company_name": self.company_name,
+            "verification_url": self.verification_url,
+            "expires_in_hours": self.expires_in_hours,
+        }))
     }
+}
 
+/// Password reset email template
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PasswordResetEmailTemplate {
+    pub user_name: String,
+    pub company_name: String,
+    pub reset_url: String,
+    pub expires_in_hours: u32,
+    pub source_ip: Option<String>,
+}
+
+impl EmailTemplate for PasswordResetEmailTemplate {
+    fn template_name(&self) -> &'static str {
+        "password_reset"
+    }
+
+    fn variables(&self) -> TemplateValues {
+        values(json!({
+            "user_name": self.user_name,
+            "company_name": self.company_name,
+            "reset_url": self.reset_url,
+            "expires_in_hours": self.expires_in_hours,
+            "source_ip": self.source_ip,
+        }))
+    }
+}
+
+/// Welcome email template (after successful verification)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WelcomeEmailTemplate {
+    pub user_name: String,
+    pub company_name: String,
+    pub login_url: String,
+}
+
+impl EmailTemplate for WelcomeEmailTemplate {
     fn template_name(&self) -> &'static str {
         "welcome"
     }
+
+    fn variables(&self) -> TemplateValues {
+        values(json!({
+            "user_name": self.user_name,
+            "company_name": self.company_name,
+            "login_url": self.login_url,
+        }))
+    }
+}
+
+/// Password changed notification (sent after a logged-in user changes their password)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PasswordChangedEmailTemplate {
+    pub user_name: String,
+    pub company_name: String,
+    pub support_url: String,
+    pub changed_at: chrono::DateTime<chrono::Utc>,
+    pub source_ip: Option<String>,
+}
+
+impl EmailTemplate for PasswordChangedEmailTemplate {
+    fn template_name(&self) -> &'static str {
+        "password_changed"
+    }
+
+    fn variables(&self) -> TemplateValues {
+        values(json!({
+            "user_name": self.user_name,
+            "company_name": self.company_name,
+            "support_url": self.support_url,
+            "changed_at": self.changed_at.format("%Y-%m-%d %H:%M UTC").to_string(),
+            "source_ip": self.source_ip,
+        }))
+    }
+}
+
+/// Invitation to join a tenant, sent on invite and as the halfway reminder
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InvitationEmailTemplate {
+    pub user_name: String,
+    pub inviter_name: String,
+    /// Name of the tenant the user is invited to
+    pub company_name: String,
+    pub accept_url: String,
+    pub expires_at: chrono::DateTime<chrono::Utc>,
+    /// Sent because the invitation is still open halfway to its expiry
+    pub reminder: bool,
+}
+
+impl EmailTemplate for InvitationEmailTemplate {
+    fn template_name(&self) -> &'static str {
+        if self.reminder {
+            "invitation_reminder"
+        } else {
+            "invitation"
+        }
+    }
+
+    fn registered_name(&self) -> &'static str {
+        "invitation"
+    }
+
+    fn variables(&self) -> TemplateValues {
+        values(json!({
+            "user_name": self.user_name,
+            "inviter_name": self.inviter_name,
+            "company_name": self.company_name,
+            "accept_url": self.accept_url,
+            "expires_at": self.expires_at.format("%Y-%m-%d %H:%M UTC").to_string(),
+            "reminder": self.reminder,
+        }))
+    }
 }
 
 #[cfg(test)]
@@ -426,4 +915,48 @@ mod tests {
         assert!(html.contains("192.168.1.1"));
         assert!(html.contains("1 hours"));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_password_changed_template() {
+        let template = PasswordChangedEmailTemplate {
+            user_name: "Jane Smith".to_string(),
+            company_name: "Test Company".to_string(),
+            support_url: "https://example.com/support".to_string(),
+            changed_at: chrono::Utc::now(),
+            source_ip: Some("192.168.1.1".to_string()),
+        };
+
+        assert!(template.subject().contains("Test Company"));
+        assert_eq!(template.template_name(), "password_changed");
+
+        let html = template.html_body();
+        assert!(html.contains("Jane Smith"));
+        assert!(html.contains("href=\"https://example.com/support\""));
+        assert!(html.contains("192.168.1.1"));
+
+        let text = template.text_body();
+        assert!(text.contains("https://example.com/support"));
+    }
+
+    #[test]
+    fn test_invitation_template() {
+        let template = InvitationEmailTemplate {
+            user_name: "Jane Smith".to_string(),
+            inviter_name: "John Doe".to_string(),
+            company_name: "Test Company".to_string(),
+            accept_url: "https://example.com/invitations/abc123".to_string(),
+            expires_at: chrono::Utc::now(),
+            reminder: false,
+        };
+
+        assert!(template.subject().contains("John Doe"));
+        assert_eq!(template.template_name(), "invitation");
+        assert!(template.html_body().contains("href=\"https://example.com/invitations/abc123\""));
+        assert!(!template.text_body().contains("reminder"));
+
+        let reminder = InvitationEmailTemplate { reminder: true, ..template };
+        assert!(reminder.subject().starts_with("Reminder"));
+        assert_eq!(reminder.template_name(), "invitation_reminder");
+        assert!(reminder.text_body().contains("This is a reminder"));
+    }
+}
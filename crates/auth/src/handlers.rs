@@ -1,6 +1,7 @@
 use crate::{
     dto::*,
     middleware::{auth_middleware, AuthState},
+    models::AccountState,
     service::{AuthService, LoginOrTwoFactorResponse},
 };
 use axum::{
@@ -29,6 +30,8 @@ pub struct PaginationParams {
     pub page: u32,
     #[serde(default = "default_limit")]
     pub limit: u32,
+    /// Only users in this state: `active`, `deactivated`, `soft_deleted` or `locked`
+    pub state: Option<AccountState>,
 }
 
 fn default_page() -> u32 { 1 }
@@ -48,8 +51,10 @@ pub fn auth_routes() -> Router<SharedAuthService> {
         .route("/auth/validate-reset-token/:token", get(validate_reset_token))
         // Protected endpoints - will be protected when auth_routes_with_middleware is used
         .route("/auth/logout", post(logout))
+        .route("/auth/change-password", post(change_password))
         .route("/users", get(list_users).post(invite_user))
         .route("/users/:id", get(get_user).put(update_user).delete(delete_user))
+        .route("/users/:id/reactivate", post(reactivate_user))
         .route("/users/:id/roles", post(assign_role).delete(remove_role))
         .route("/users/:id/enable-2fa", post(enable_2fa))
         .route("/users/:id/disable-2fa", post(disable_2fa))
@@ -77,10 +82,12 @@ pub fn auth_routes_with_middleware(auth_service: SharedAuthService) -> Router<Sh
     let protected_routes = Router::new()
         // Basic protected endpoints - require authentication only
         .route("/auth/logout", post(logout))
+        .route("/auth/change-password", post(change_password))
         .route("/auth/stop-impersonation", post(stop_impersonation))
         // User management endpoints
         .route("/users", get(list_users).post(invite_user))
         .route("/users/:id", get(get_user).put(update_user).delete(delete_user))
+        .route("/users/:id/reactivate", post(reactivate_user))
         .route("/users/:id/roles", post(assign_role).delete(remove_role))
         .route("/users/:id/enable-2fa", post(enable_2fa))
         .route("/users/:id/disable-2fa", post(disable_2fa))
@@ -97,6 +104,7 @@ pub fn auth_routes_with_middleware(auth_service: SharedAuthService) -> Router<Sh
                 jwt_service: auth_service.jwt_service(),
                 db: auth_service.db(),
                 redis: auth_service.redis(),
+                tenants: auth_service.tenant_resolver(),
             },
             auth_middleware
         ))
@@ -256,6 +264,43 @@ async fn logout(
     Ok((jar, StatusCode::NO_CONTENT).into_response())
 }
 
+async fn change_password(
+    State(service): State<SharedAuthService>,
+    jar: CookieJar,
+    headers: HeaderMap,
+    ctx: RequestContext,
+    Json(request): Json<ChangePasswordRequest>,
+) -> Result<Response, AppError> {
+    let tenant_context = ctx.tenant_context
+        .ok_or_else(|| Error::new(erp_core::ErrorCode::MissingRequiredField, "Missing tenant context"))?;
+    let user_id = ctx.user_id
+        .ok_or_else(|| Error::new(erp_core::ErrorCode::MissingRequiredField, "Missing user ID in token"))?;
+    let client_ip = extract_client_ip(&headers);
+    let user_agent = extract_user_agent(&headers);
+
+    info!("Password change for user: {} in tenant: {}", user_id, tenant_context.tenant_id.0);
+
+    let response = service.change_password(&tenant_context, user_id, request, client_ip, user_agent).await?;
+
+    // The old refresh token no longer works; hand out the one for the new session
+    let refresh_cookie = Cookie::build(("refresh_token", response.refresh_token))
+        .http_only(true)
+        .secure(true)
+        .same_site(SameSite::Strict)
+        .path("/api/v1/auth")
+        .max_age(time::Duration::days(30))
+        .build();
+
+    let jar = jar.add(refresh_cookie);
+
+    Ok((
+        jar,
+        Json(serde_json::json!({
+            "access_token": response.access_token
+        }))
+    ).into_response())
+}
+
 async fn list_users(
     State(service): State<SharedAuthService>,
     ctx: RequestContext,
@@ -269,12 +314,13 @@ async fn list_users(
     
     // Page is 1-based in request, but 0-based for service
     let page = params.page.saturating_sub(1);
-    let users = service.list_users(&tenant_context, page, params.limit).await?;
+    let users = service.list_users(&tenant_context, page, params.limit, params.state).await?;
     
     Ok(Json(serde_json::json!({
         "users": users,
         "page": params.page,
-        "limit": params.limit
+        "limit": params.limit,
+        "state": params.state
     })))
 }
 
@@ -304,7 +350,7 @@ async fn invite_user(
     let tenant_context = ctx.tenant_context
         .ok_or_else(|| Error::new(erp_core::ErrorCode::MissingRequiredField, "Missing tenant context"))?;
     
-    let user = service.invite_user(&tenant_context, request).await?;
+    let user = service.invite_user(&tenant_context, request, ctx.user_id).await?;
     Ok(Json(user))
 }
 
@@ -339,6 +385,21 @@ async fn delete_user(
     Ok(StatusCode::NO_CONTENT)
 }
 
+async fn reactivate_user(
+    State(service): State<SharedAuthService>,
+    ctx: RequestContext,
+    Path(user_id): Path<Uuid>,
+) -> Result<Json<UserResponse>, AppError> {
+    // Check permission
+    check_permission(&ctx, "users", "update")?;
+    
+    let tenant_context = ctx.tenant_context
+        .ok_or_else(|| Error::new(erp_core::ErrorCode::MissingRequiredField, "Missing tenant context"))?;
+    
+    let user = service.reactivate_user(&tenant_context, user_id).await?;
+    Ok(Json(user))
+}
+
 async fn assign_role(
     State(service): State<SharedAuthService>,
     ctx: RequestContext,
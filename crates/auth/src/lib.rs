@@ -9,16 +9,21 @@ pub mod email;
 pub mod tokens;
 pub mod workflows;
 pub mod validation;
+pub mod password_policy;
+pub mod role_catalog;
+pub mod session_limit;
+pub mod backup_codes;
 
 pub use models::*;
 pub use repository::{AuthRepository, UserRepository};
 pub use service::{AuthService, LoginOrTwoFactorResponse};
 pub use handlers::SharedAuthService;
-pub use middleware::{auth_middleware, require_permission, AuthState};
+pub use middleware::{auth_middleware, portal_guard, require_permission, AuthState};
 pub use openapi::AuthApiDoc;
 pub use email::{EmailService, EmailTemplate};
 pub use tokens::{TokenManager, TokenPurpose, TokenData};
-pub use workflows::{PasswordResetWorkflow, EmailVerificationWorkflow, PasswordResetConfig, EmailVerificationConfig};
+pub use workflows::{PasswordResetWorkflow, PasswordChangeWorkflow, EmailVerificationWorkflow, InvitationWorkflow, PasswordResetConfig, PasswordChangeConfig, EmailVerificationConfig, InvitationWorkflowConfig};
+pub use password_policy::PasswordPolicy;
 
 #[cfg(test)]
 mod tests;
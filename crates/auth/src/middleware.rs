@@ -1,24 +1,38 @@
+use crate::repository::AuthRepository;
 use axum::{
-    extract::{Request, State},
-    http::{header::AUTHORIZATION, StatusCode},
+    extract::{OriginalUri, Request, State},
+    http::{header::{AUTHORIZATION, UPGRADE}, HeaderValue, StatusCode},
     middleware::Next,
     response::{IntoResponse, Response},
     Json,
 };
+use chrono::Utc;
 use erp_core::{
-    security::JwtService,
-    DatabasePool, Error, Permission, RequestContext, TenantContext, TenantId, UserId,
+    permission_usage::PermissionUsageRecorder,
+    portal::{is_portal_route, portal_permissions},
+    security::{
+        is_past_grace, is_token_superseded, revoked_session_key, token_cutoff_keys, token_revoked_after_key, JwtService, TokenExpiryHint,
+    },
+    DatabasePool, JwtClaims, Permission, RequestContext, TenantContextResolver, UserId,
 };
 use redis::aio::ConnectionManager;
 use std::sync::Arc;
 use tracing::{error, warn};
 use uuid::Uuid;
 
+/// Seconds until the access token of the request expires
+pub const TOKEN_EXPIRES_IN_HEADER: &str = "x-token-expires-in";
+/// `true` once the access token is close enough to expiry to be refreshed
+pub const TOKEN_REFRESH_ADVISED_HEADER: &str = "x-token-refresh-advised";
+/// Fresh access token handed out to tenants with sliding sessions
+pub const REFRESHED_ACCESS_TOKEN_HEADER: &str = "x-refreshed-access-token";
+
 #[derive(Clone)]
 pub struct AuthState {
     pub jwt_service: Arc<JwtService>,
     pub db: Arc<DatabasePool>,
     pub redis: ConnectionManager,
+    pub tenants: Arc<TenantContextResolver>,
 }
 
 pub async fn auth_middleware(
@@ -41,10 +55,160 @@ pub async fn auth_middleware(
         }
     };
 
+    let now = Utc::now().timestamp();
+    let hint = state.jwt_service.expiry_hint(&claims, now);
+    let sliding_candidate = (hint.refresh_advised && sliding_refresh_allowed(&request, &claims)).then(|| claims.clone());
+
+    let context = match request_context(&state, claims).await {
+        Ok(context) => context,
+        Err(response) => return Ok(response),
+    };
+    if let Some(response) = reject_portal_route(&context, &request) {
+        return Ok(response);
+    }
+
+    // Insert context and the actor of its writes into request extensions
+    request.extensions_mut().insert(context.actor());
+    request.extensions_mut().insert(context);
+
+    let mut response = next.run(request).await;
+    set_expiry_headers(&mut response, hint);
+    if let Some(claims) = sliding_candidate {
+        if let Some(token) = sliding_refresh(&state, &claims, hint, now).await {
+            if let Ok(value) = HeaderValue::from_str(&token) {
+                response.headers_mut().insert(REFRESHED_ACCESS_TOKEN_HEADER, value);
+            }
+        }
+    }
+
+    Ok(response)
+}
+
+/// Tells the client how long its access token has left and whether to refresh it
+fn set_expiry_headers(response: &mut Response, hint: TokenExpiryHint) {
+    let headers = response.headers_mut();
+    headers.insert(TOKEN_EXPIRES_IN_HEADER, HeaderValue::from(hint.expires_in));
+    if hint.refresh_advised {
+        headers.insert(TOKEN_REFRESH_ADVISED_HEADER, HeaderValue::from_static("true"));
+    }
+}
+
+/// Whether a request may get its token refreshed by a sliding session: only
+/// over HTTPS, so the new token never travels in clear text, and never for
+/// impersonation tokens, which must not outlive the session they were issued for
+fn sliding_refresh_allowed(request: &Request, claims: &JwtClaims) -> bool {
+    claims.impersonator_id.is_none() && is_https(request)
+}
+
+/// The request reached us over HTTPS, directly or through a TLS-terminating proxy
+fn is_https(request: &Request) -> bool {
+    if request.uri().scheme_str() == Some("https") {
+        return true;
+    }
+    request
+        .headers()
+        .get("x-forwarded-proto")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(',').next())
+        .is_some_and(|proto| proto.trim().eq_ignore_ascii_case("https"))
+}
+
+/// Replaces the request's access token if its tenant has sliding sessions.
+///
+/// The old token is not revoked outright: it stays valid for the grace
+/// period, so requests the client already sent with it still pass. Only the
+/// first request to claim the rotation of a token gets the new one.
+async fn sliding_refresh(state: &AuthState, claims: &JwtClaims, hint: TokenExpiryHint, now: i64) -> Option<String> {
+    // The request itself may have signed the token out
+    if check_token_revoked(&state.redis, &claims.jti).await
+        || check_session_revoked(&state.redis, claims.sid.as_deref()).await
+    {
+        return None;
+    }
+    let tenant_id = Uuid::parse_str(&claims.tenant_id).ok()?;
+    match AuthRepository::new(state.db.as_ref().clone()).tenant_sliding_sessions(tenant_id).await {
+        Ok(true) => {}
+        Ok(false) => return None,
+        Err(e) => {
+            error!("Failed to load sliding session setting: {}", e);
+            return None;
+        }
+    }
+
+    let token = match state.jwt_service.refresh_access_token(claims) {
+        Ok(token) => token,
+        Err(e) => {
+            warn!("Sliding session refresh failed: {}", e);
+            return None;
+        }
+    };
+
+    let revoked_after = now + state.jwt_service.sliding_grace_seconds();
+    let mut conn = state.redis.clone();
+    let claimed: redis::RedisResult<Option<String>> = redis::cmd("SET")
+        .arg(token_revoked_after_key(&claims.jti))
+        .arg(revoked_after)
+        .arg("NX")
+        .arg("EX")
+        .arg(hint.expires_in.max(1))
+        .query_async(&mut conn)
+        .await;
+    match claimed {
+        Ok(Some(_)) => Some(token),
+        Ok(None) => None,
+        Err(e) => {
+            error!("Failed to record sliding session refresh: {}", e);
+            None
+        }
+    }
+}
+
+/// Holds customer portal tokens to the portal rules on routes that do not
+/// require authentication themselves
+///
+/// A request with a valid portal token must pass the same checks as under
+/// [`auth_middleware`], may only reach portal routes and gets its
+/// `RequestContext`, so handlers can scope their data to the customer. A
+/// request with another valid, unrevoked token gets only its `ActorContext`,
+/// so writes record who made them. Any other request passes unchanged.
+pub async fn portal_guard(
+    State(state): State<AuthState>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, StatusCode> {
+    let claims = extract_token(&request).and_then(|token| state.jwt_service.verify_access_token(&token).ok());
+    let Some(claims) = claims else {
+        return Ok(next.run(request).await);
+    };
+    if claims.portal.is_none() {
+        if let Ok(context) = request_context(&state, claims).await {
+            request.extensions_mut().insert(context.actor());
+        }
+        return Ok(next.run(request).await);
+    }
+
+    let context = match request_context(&state, claims).await {
+        Ok(context) => context,
+        Err(response) => return Ok(response),
+    };
+    if let Some(response) = reject_portal_route(&context, &request) {
+        return Ok(response);
+    }
+
+    request.extensions_mut().insert(context.actor());
+    request.extensions_mut().insert(context);
+
+    Ok(next.run(request).await)
+}
+
+/// Check a verified token against revocation and build the request context
+async fn request_context(state: &AuthState, claims: JwtClaims) -> Result<RequestContext, Response> {
     // Check if token is revoked
-    let is_revoked = check_token_revoked(&state.redis, &claims.jti).await;
-    if is_revoked {
-        return Ok(unauthorized_response("Token has been revoked"));
+    let is_revoked = check_token_revoked(&state.redis, &claims.jti).await
+        || check_token_replaced(&state.redis, &claims.jti).await
+        || check_session_revoked(&state.redis, claims.sid.as_deref()).await;
+    if is_revoked || check_token_superseded(&state.redis, &claims.tenant_id, &claims.sub, claims.iat).await {
+        return Err(unauthorized_response("Token has been revoked"));
     }
 
     // Parse IDs
@@ -52,7 +216,7 @@ pub async fn auth_middleware(
         Ok(id) => id,
         Err(_) => {
             error!("Invalid tenant ID in token: {}", claims.tenant_id);
-            return Ok(unauthorized_response("Invalid token claims"));
+            return Err(unauthorized_response("Invalid token claims"));
         }
     };
 
@@ -60,22 +224,31 @@ pub async fn auth_middleware(
         Ok(id) => id,
         Err(_) => {
             error!("Invalid user ID in token: {}", claims.sub);
-            return Ok(unauthorized_response("Invalid token claims"));
+            return Err(unauthorized_response("Invalid token claims"));
         }
     };
 
     // Get tenant context
-    let tenant = match get_tenant_context(&state.db, tenant_id).await {
+    let tenant = match state.tenants.resolve(tenant_id).await {
         Ok(tenant) => tenant,
+        Err(e) if e.code == erp_core::ErrorCode::ResourceNotFound => {
+            warn!("Token for unknown tenant {}", tenant_id);
+            return Err(unauthorized_response("Tenant not found"));
+        }
         Err(e) => {
             error!("Failed to get tenant context: {}", e);
-            return Ok(StatusCode::INTERNAL_SERVER_ERROR.into_response());
+            return Err(StatusCode::INTERNAL_SERVER_ERROR.into_response());
         }
     };
 
+    // Portal tokens never hold more than the portal template
+    let granted = match claims.portal {
+        Some(_) => portal_permissions(&claims.permissions),
+        None => claims.permissions,
+    };
+
     // Parse permissions
-    let permissions: Vec<Permission> = claims
-        .permissions
+    let permissions: Vec<Permission> = granted
         .iter()
         .filter_map(|p| {
             let parts: Vec<&str> = p.split(':').collect();
@@ -95,19 +268,33 @@ pub async fn auth_middleware(
         .map(UserId);
 
     // Create request context
-    let context = RequestContext {
+    Ok(RequestContext {
         tenant_context: Some(tenant),
         user_id: Some(user_id),
         jti: Some(claims.jti.clone()),
+        session_id: claims.sid.clone(),
         permissions,
         impersonator_id,
+        portal: claims.portal,
         request_id: Uuid::new_v4().to_string(),
-    };
+    })
+}
 
-    // Insert context into request extensions
-    request.extensions_mut().insert(context);
+/// Portal accounts may only call portal routes, whatever their roles say
+fn reject_portal_route(context: &RequestContext, request: &Request) -> Option<Response> {
+    context.portal?;
+    // Nested routers see their path without the prefix they are nested under
+    let path = request
+        .extensions()
+        .get::<OriginalUri>()
+        .map(|uri| uri.0.path())
+        .unwrap_or_else(|| request.uri().path());
+    if is_portal_route(request.method().as_str(), path) {
+        return None;
+    }
 
-    Ok(next.run(request).await)
+    warn!("Portal user {:?} denied {} {}", context.user_id, request.method(), path);
+    Some(forbidden_response("Not available to customer portal accounts"))
 }
 
 pub async fn require_permission_middleware(
@@ -136,6 +323,15 @@ pub async fn require_permission_middleware(
         )));
     }
 
+    // Usage for least-privilege reviews; an impersonation token counts as an admin override
+    if let (Some(recorder), Some(tenant), Some(user_id)) = (
+        request.extensions().get::<PermissionUsageRecorder>(),
+        &context.tenant_context,
+        context.user_id,
+    ) {
+        recorder.record(tenant.tenant_id.0, user_id, &required_permission, context.impersonator_id.is_some());
+    }
+
     Ok(next.run(request).await)
 }
 
@@ -182,6 +378,24 @@ fn extract_token(request: &Request) -> Option<String> {
                 None
             }
         })
+        .or_else(|| websocket_query_token(request))
+}
+
+/// The `access_token` query parameter of a WebSocket upgrade request;
+/// browsers cannot set the `Authorization` header on one. Other requests
+/// must not carry tokens in URLs, which end up in logs.
+fn websocket_query_token(request: &Request) -> Option<String> {
+    let upgrade = request.headers().get(UPGRADE).and_then(|value| value.to_str().ok())?;
+    if !upgrade.eq_ignore_ascii_case("websocket") {
+        return None;
+    }
+    request
+        .uri()
+        .query()?
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("access_token="))
+        .filter(|token| !token.is_empty())
+        .map(str::to_string)
 }
 
 async fn check_token_revoked(redis: &ConnectionManager, jti: &str) -> bool {
@@ -197,25 +411,46 @@ async fn check_token_revoked(redis: &ConnectionManager, jti: &str) -> bool {
     }
 }
 
-async fn get_tenant_context(_db: &DatabasePool, tenant_id: Uuid) -> Result<TenantContext, Error> {
-    // TODO: Re-enable once sqlx query cache is fixed
-    /*
-    let tenant = sqlx::query!(
-        "SELECT schema_name FROM public.tenants WHERE id = $1 AND status = 'active'",
-        tenant_id
-    )
-    .fetch_optional(&db.main_pool)
-    .await?
-    .ok_or_else(|| Error::new(erp_core::ErrorCode::ResourceNotFound, "Tenant not found or inactive"))?;
-    */
+/// Whether the session the token is bound to was signed out
+async fn check_session_revoked(redis: &ConnectionManager, session_id: Option<&str>) -> bool {
+    let Some(session_id) = session_id else {
+        return false;
+    };
+    let mut conn = redis.clone();
+    match redis::AsyncCommands::exists::<_, bool>(&mut conn, revoked_session_key(session_id)).await {
+        Ok(exists) => exists,
+        Err(e) => {
+            error!("Failed to check session revocation: {}", e);
+            false // Allow on Redis error to prevent complete lockout
+        }
+    }
+}
+
+/// Whether a sliding session refresh replaced the token and its grace period is over
+async fn check_token_replaced(redis: &ConnectionManager, jti: &str) -> bool {
+    let mut conn = redis.clone();
+    match redis::AsyncCommands::get::<_, Option<i64>>(&mut conn, token_revoked_after_key(jti)).await {
+        Ok(revoked_after) => is_past_grace(Utc::now().timestamp(), revoked_after),
+        Err(e) => {
+            error!("Failed to check token replacement: {}", e);
+            false // Allow on Redis error to prevent complete lockout
+        }
+    }
+}
 
-    // Temporary placeholder
-    let schema_name = format!("tenant_{}", tenant_id.to_string().replace('-', "_"));
+/// Whether the token predates the user's last password change or the
+/// tenant's last sandbox reset
+async fn check_token_superseded(redis: &ConnectionManager, tenant_id: &str, user_id: &str, issued_at: i64) -> bool {
+    let keys = token_cutoff_keys(tenant_id, user_id);
 
-    Ok(TenantContext {
-        tenant_id: TenantId(tenant_id),
-        schema_name,
-    })
+    let mut conn = redis.clone();
+    match redis::AsyncCommands::mget::<_, Vec<Option<i64>>>(&mut conn, &keys).await {
+        Ok(cutoffs) => is_token_superseded(issued_at, cutoffs.into_iter().flatten().max()),
+        Err(e) => {
+            error!("Failed to check token cutoff: {}", e);
+            false // Allow on Redis error to prevent complete lockout
+        }
+    }
 }
 
 fn extract_client_id(request: &Request) -> String {
@@ -286,4 +521,161 @@ fn too_many_requests_response() -> Response {
         })),
     )
         .into_response()
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, routing::get, Extension, Router};
+    use chrono::Utc;
+    use erp_core::permission_usage::{PermissionUsageReport, RoleGrants, UserPermissionUsage};
+    use erp_core::{TenantContext, TenantId};
+    use std::collections::BTreeMap;
+    use tower::ServiceExt;
+
+    fn context(tenant_id: Uuid, user_id: Uuid, permissions: &[&str], impersonated: bool) -> RequestContext {
+        let mut context = RequestContext::new();
+        context.tenant_context = Some(TenantContext {
+            tenant_id: TenantId(tenant_id),
+            schema_name: "tenant_test".to_string(),
+        });
+        context.user_id = Some(user_id);
+        context.permissions = permissions
+            .iter()
+            .map(|p| {
+                let (resource, action) = p.split_once(':').unwrap();
+                Permission::new(resource, action)
+            })
+            .collect();
+        context.impersonator_id = impersonated.then(|| UserId(Uuid::new_v4()));
+        context
+    }
+
+    #[tokio::test]
+    async fn test_granted_permissions_are_recorded_for_usage_review() {
+        let (recorder, mut uses) = PermissionUsageRecorder::new(100, "test").unwrap();
+        let app = Router::new()
+            .route("/products", get(|| async { "ok" }).layer(axum::middleware::from_fn(require_permission("products:write"))))
+            .route("/returns", get(|| async { "ok" }).layer(axum::middleware::from_fn(require_permission("inventory:write"))))
+            .layer(Extension(recorder));
+
+        let (tenant_id, clerk) = (Uuid::new_v4(), Uuid::new_v4());
+        let held = ["products:write", "inventory:write", "customers:delete"];
+        let call = |path: &str, context: RequestContext| {
+            let mut request = Request::builder().uri(path).body(Body::empty()).unwrap();
+            request.extensions_mut().insert(context);
+            app.clone().oneshot(request)
+        };
+
+        for _ in 0..2 {
+            let response = call("/products", context(tenant_id, clerk, &held, false)).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+        // Only ever reached by an administrator impersonating the clerk
+        let response = call("/returns", context(tenant_id, clerk, &held, true)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        // Denied requests are not usage
+        let response = call("/returns", context(tenant_id, clerk, &["products:write"], false)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let mut counts: BTreeMap<String, (i64, i64)> = BTreeMap::new();
+        while let Ok(usage) = uses.try_recv() {
+            assert_eq!((usage.tenant_id, usage.user_id), (tenant_id, clerk));
+            let entry = counts.entry(usage.permission).or_default();
+            if usage.via_override { entry.1 += 1 } else { entry.0 += 1 }
+        }
+        let today = Utc::now().date_naive();
+        let rows: Vec<UserPermissionUsage> = counts
+            .into_iter()
+            .map(|(permission, (uses, override_uses))| UserPermissionUsage {
+                user_id: clerk,
+                permission,
+                uses,
+                override_uses,
+                last_used: today,
+            })
+            .collect();
+        let roles = vec![RoleGrants {
+            role_id: Uuid::new_v4(),
+            role: "clerk".to_string(),
+            permissions: held.iter().map(|p| p.to_string()).collect(),
+            members: vec![clerk],
+        }];
+
+        let report = PermissionUsageReport::build(tenant_id, today, roles, &rows);
+        let clerk_role = &report.roles[0];
+        assert_eq!(clerk_role.unused, vec!["customers:delete".to_string()]);
+        assert_eq!(clerk_role.override_only, vec!["inventory:write".to_string()]);
+        let products = clerk_role.permissions.iter().find(|p| p.permission == "products:write").unwrap();
+        assert_eq!((products.uses, products.override_uses), (2, 0));
+    }
+
+    fn claims(impersonator_id: Option<String>) -> JwtClaims {
+        JwtClaims {
+            sub: Uuid::new_v4().to_string(),
+            tenant_id: Uuid::new_v4().to_string(),
+            roles: vec![],
+            permissions: vec![],
+            exp: 1_900,
+            iat: 1_000,
+            jti: Uuid::new_v4().to_string(),
+            impersonator_id,
+            portal: None,
+            sid: None,
+        }
+    }
+
+    #[test]
+    fn test_expiry_headers_advise_a_refresh_only_near_expiry() {
+        let mut response = StatusCode::OK.into_response();
+        set_expiry_headers(&mut response, TokenExpiryHint { expires_in: 600, refresh_advised: false });
+        assert_eq!(response.headers()[TOKEN_EXPIRES_IN_HEADER], "600");
+        assert!(response.headers().get(TOKEN_REFRESH_ADVISED_HEADER).is_none());
+
+        let mut response = StatusCode::NOT_FOUND.into_response();
+        set_expiry_headers(&mut response, TokenExpiryHint { expires_in: 0, refresh_advised: true });
+        assert_eq!(response.headers()[TOKEN_EXPIRES_IN_HEADER], "0");
+        assert_eq!(response.headers()[TOKEN_REFRESH_ADVISED_HEADER], "true");
+    }
+
+    #[test]
+    fn test_sliding_refresh_needs_https_and_no_impersonation() {
+        let request = |uri: &str, proto: Option<&str>| {
+            let mut builder = Request::builder().uri(uri);
+            if let Some(proto) = proto {
+                builder = builder.header("x-forwarded-proto", proto);
+            }
+            builder.body(Body::empty()).unwrap()
+        };
+        let user = claims(None);
+
+        assert!(sliding_refresh_allowed(&request("https://erp.example.com/products", None), &user));
+        assert!(sliding_refresh_allowed(&request("/products", Some("https")), &user));
+        assert!(sliding_refresh_allowed(&request("/products", Some("HTTPS, http")), &user));
+        assert!(!sliding_refresh_allowed(&request("/products", None), &user));
+        assert!(!sliding_refresh_allowed(&request("/products", Some("http")), &user));
+
+        let impersonated = claims(Some(Uuid::new_v4().to_string()));
+        assert!(!sliding_refresh_allowed(&request("/products", Some("https")), &impersonated));
+    }
+
+    #[test]
+    fn test_query_token_is_only_read_on_websocket_upgrades() {
+        let request = |upgrade: Option<&str>| {
+            let mut builder = Request::builder().uri("/inventory/alerts/stream?severity=high&access_token=abc.def.ghi");
+            if let Some(upgrade) = upgrade {
+                builder = builder.header(UPGRADE, upgrade);
+            }
+            builder.body(Body::empty()).unwrap()
+        };
+
+        assert_eq!(extract_token(&request(Some("websocket"))).as_deref(), Some("abc.def.ghi"));
+        assert_eq!(extract_token(&request(Some("WebSocket"))).as_deref(), Some("abc.def.ghi"));
+        assert_eq!(extract_token(&request(None)), None);
+        assert_eq!(extract_token(&request(Some("h2c"))), None);
+
+        let mut with_header = request(Some("websocket"));
+        with_header.headers_mut().insert(AUTHORIZATION, HeaderValue::from_static("Bearer from.the.header"));
+        assert_eq!(extract_token(&with_header).as_deref(), Some("from.the.header"));
+    }
+}
@@ -1,6 +1,7 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
+use utoipa::ToSchema;
 use uuid::Uuid;
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
@@ -28,6 +29,68 @@ pub struct User {
     pub last_login_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+/// Lifecycle state of a user account, derived from `deleted_at`,
+/// `is_active` and `locked_until` in that order of precedence
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountState {
+    Active,
+    /// Switched off by an administrator; keeps its seat free
+    Deactivated,
+    /// Deleted, kept for the audit trail and reactivation
+    SoftDeleted,
+    /// Temporarily locked until `locked_until`
+    Locked,
+}
+
+impl AccountState {
+    pub const ALL: [AccountState; 4] = [
+        AccountState::Active,
+        AccountState::Deactivated,
+        AccountState::SoftDeleted,
+        AccountState::Locked,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AccountState::Active => "active",
+            AccountState::Deactivated => "deactivated",
+            AccountState::SoftDeleted => "soft_deleted",
+            AccountState::Locked => "locked",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|state| state.as_str() == value)
+    }
+
+    /// Why a login is refused in this state, `None` when it is not.
+    ///
+    /// Callers of the login endpoint always see "Invalid credentials"; the
+    /// code is attached to the error as `login_denial` metadata and recorded
+    /// in the audit log, so only administrators can tell the states apart.
+    pub fn login_denial(&self) -> Option<&'static str> {
+        match self {
+            AccountState::Active => None,
+            AccountState::Deactivated => Some("ACCOUNT_DEACTIVATED"),
+            AccountState::SoftDeleted => Some("ACCOUNT_DELETED"),
+            AccountState::Locked => Some("ACCOUNT_LOCKED"),
+        }
+    }
+
+    /// Whether `reactivate_user` applies
+    pub fn can_reactivate(&self) -> bool {
+        matches!(self, AccountState::Deactivated | AccountState::SoftDeleted)
+    }
+}
+
+impl std::fmt::Display for AccountState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
@@ -65,6 +128,38 @@ impl std::fmt::Display for Permission {
     }
 }
 
+/// A customer contact's limited account, see [`erp_core::portal`]
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct PortalAccount {
+    pub id: Uuid,
+    pub tenant_id: Uuid,
+    pub user_id: Uuid,
+    pub customer_id: Uuid,
+    pub contact_id: Option<Uuid>,
+    pub email: String,
+    pub include_children: bool,
+    /// `invited`, `active` or `revoked`
+    pub status: String,
+    pub invited_by: Option<Uuid>,
+    pub invited_at: DateTime<Utc>,
+    pub accepted_at: Option<DateTime<Utc>>,
+    pub revoked_by: Option<Uuid>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+impl PortalAccount {
+    pub fn is_active(&self) -> bool {
+        self.status == "active"
+    }
+
+    pub fn scope(&self) -> erp_core::portal::PortalScope {
+        erp_core::portal::PortalScope {
+            customer_id: self.customer_id,
+            include_children: self.include_children,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct UserRole {
     pub user_id: Uuid,
@@ -105,6 +200,18 @@ impl User {
         }
     }
 
+    pub fn state(&self) -> AccountState {
+        if self.deleted_at.is_some() {
+            AccountState::SoftDeleted
+        } else if !self.is_active {
+            AccountState::Deactivated
+        } else if self.is_locked() {
+            AccountState::Locked
+        } else {
+            AccountState::Active
+        }
+    }
+
     pub fn has_2fa_enabled(&self) -> bool {
         self.two_factor_secret_encrypted.is_some() && self.two_factor_enabled_at.is_some()
     }
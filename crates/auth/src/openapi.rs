@@ -1,6 +1,7 @@
 #![allow(dead_code)]
 
 use crate::dto::*;
+use crate::models::AccountState;
 use utoipa::OpenApi;
 
 #[derive(OpenApi)]
@@ -33,18 +34,22 @@ use utoipa::OpenApi;
         // resend_verification,
         // validate_reset_token,
         // logout,
-        // list_users,
         // get_user,
         // invite_user,
         // update_user,
         // delete_user,
-        // list_roles,
         // create_role,
         // get_role,
         // update_role,
         // delete_role,
         // list_permissions,
         // impersonate,
+        list_sessions,
+        revoke_session,
+        revoke_other_sessions,
+        list_user_sessions,
+        revoke_user_session,
+        revoke_user_sessions,
     ),
     components(
         schemas(
@@ -56,6 +61,7 @@ use utoipa::OpenApi;
             Verify2FARequest,
             ForgotPasswordRequest,
             ResetPasswordRequest,
+            ChangePasswordRequest,
             VerifyEmailRequest,
             ResendVerificationRequest,
             EmailVerificationResponse,
@@ -67,8 +73,12 @@ use utoipa::OpenApi;
             UpdateRoleRequest,
             ImpersonateRequest,
             UserResponse,
+            AccountState,
             RoleResponse,
             PermissionResponse,
+            SessionResponse,
+            SessionListResponse,
+            RevokeSessionsResponse,
         )
     ),
     tags(
@@ -166,25 +176,120 @@ async fn refresh_token() {}
 )]
 async fn logout() {}
 
-/// List all users
+/// List the signed-in user's sessions
 #[utoipa::path(
     get,
-    path = "/api/v1/users",
+    path = "/api/v1/auth/sessions",
     params(
-        ("page" = u32, Query, description = "Page number"),
-        ("limit" = u32, Query, description = "Items per page"),
+        ("X-Tenant-Id" = String, Header, description = "Tenant ID")
+    ),
+    responses(
+        (status = 200, description = "Sessions, most recently active first; the one of this request has `current` set", body = SessionListResponse),
+        (status = 401, description = "Unauthorized"),
+    ),
+    tag = "auth",
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+async fn list_sessions() {}
+
+/// Sign out one of the signed-in user's sessions
+#[utoipa::path(
+    delete,
+    path = "/api/v1/auth/sessions/{session_id}",
+    params(
+        ("session_id" = String, Path, description = "Session ID"),
+        ("X-Tenant-Id" = String, Header, description = "Tenant ID")
+    ),
+    responses(
+        (status = 200, description = "Session signed out; signing out the current session revokes this access token too", body = RevokeSessionsResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "No such session of this user"),
+    ),
+    tag = "auth",
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+async fn revoke_session() {}
+
+/// Sign out every session of the signed-in user except the current one
+#[utoipa::path(
+    delete,
+    path = "/api/v1/auth/sessions",
+    params(
+        ("X-Tenant-Id" = String, Header, description = "Tenant ID")
+    ),
+    responses(
+        (status = 200, description = "Other sessions signed out", body = RevokeSessionsResponse),
+        (status = 401, description = "Unauthorized"),
+    ),
+    tag = "auth",
+    security(
+        ("bearer_auth" = [])
+    )
+)]
+async fn revoke_other_sessions() {}
+
+/// List a user's sessions
+#[utoipa::path(
+    get,
+    path = "/api/v1/users/{id}/sessions",
+    params(
+        ("id" = Uuid, Path, description = "User ID")
     ),
     responses(
-        (status = 200, description = "Users retrieved successfully"),
+        (status = 200, description = "Sessions, most recently active first", body = SessionListResponse),
         (status = 401, description = "Unauthorized"),
         (status = 403, description = "Insufficient permissions"),
     ),
     tag = "users",
     security(
-        ("bearer_auth" = ["user:read"])
+        ("bearer_auth" = ["users:read"])
     )
 )]
-async fn list_users() {}
+async fn list_user_sessions() {}
+
+/// Revoke one of a user's sessions
+#[utoipa::path(
+    delete,
+    path = "/api/v1/users/{id}/sessions/{session_id}",
+    params(
+        ("id" = Uuid, Path, description = "User ID"),
+        ("session_id" = String, Path, description = "Session ID")
+    ),
+    responses(
+        (status = 200, description = "Session revoked along with its tokens", body = RevokeSessionsResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Insufficient permissions"),
+        (status = 404, description = "No such session of this user"),
+    ),
+    tag = "users",
+    security(
+        ("bearer_auth" = ["users:update"])
+    )
+)]
+async fn revoke_user_session() {}
+
+/// Revoke every session of a user
+#[utoipa::path(
+    delete,
+    path = "/api/v1/users/{id}/sessions",
+    params(
+        ("id" = Uuid, Path, description = "User ID")
+    ),
+    responses(
+        (status = 200, description = "Sessions revoked along with their tokens", body = RevokeSessionsResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Insufficient permissions"),
+    ),
+    tag = "users",
+    security(
+        ("bearer_auth" = ["users:update"])
+    )
+)]
+async fn revoke_user_sessions() {}
 
 /// Get user by ID
 #[utoipa::path(
@@ -266,21 +371,27 @@ async fn update_user() {}
 )]
 async fn delete_user() {}
 
-/// List all roles
+/// Reactivate a deactivated or soft-deleted user
 #[utoipa::path(
-    get,
-    path = "/api/v1/roles",
+    post,
+    path = "/api/v1/users/{id}/reactivate",
+    params(
+        ("id" = Uuid, Path, description = "User ID")
+    ),
     responses(
-        (status = 200, description = "Roles retrieved successfully", body = Vec<RoleResponse>),
+        (status = 200, description = "User reactivated; a user who never verified their email is sent a verification email", body = UserResponse),
         (status = 401, description = "Unauthorized"),
         (status = 403, description = "Insufficient permissions"),
+        (status = 404, description = "User not found"),
+        (status = 409, description = "User is neither deactivated nor deleted"),
+        (status = 507, description = "The tenant's seat limit is reached"),
     ),
-    tag = "roles",
+    tag = "users",
     security(
-        ("bearer_auth" = ["role:manage"])
+        ("bearer_auth" = ["user:update"])
     )
 )]
-async fn list_roles() {}
+async fn reactivate_user() {}
 
 /// Create a new role
 #[utoipa::path(
@@ -432,6 +543,27 @@ async fn forgot_password() {}
 )]
 async fn reset_password() {}
 
+/// Change the signed-in user's password
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/change-password",
+    request_body = ChangePasswordRequest,
+    params(
+        ("X-Tenant-Id" = String, Header, description = "Tenant ID")
+    ),
+    responses(
+        (status = 200, description = "Password changed; other sessions signed out and a new token pair issued", body = LoginResponse),
+        (status = 400, description = "Policy violation, reused password, passwords don't match or no password set yet"),
+        (status = 401, description = "Current password is incorrect"),
+        (status = 429, description = "Too many attempts"),
+    ),
+    security(
+        ("bearer_auth" = [])
+    ),
+    tag = "auth"
+)]
+async fn change_password() {}
+
 /// Verify email address
 #[utoipa::path(
     post,
@@ -0,0 +1,162 @@
+//! Password policy shared by every flow that sets a password.
+//!
+//! The defaults come from `[password_policy]`; a tenant may store its own
+//! rules in `public.tenant_password_policies`. History rules compare a new
+//! password against the current hash and the most recent previous hashes.
+
+use crate::repository::AuthRepository;
+use erp_core::{
+    error::{Error, ErrorCode, Result},
+    security::PasswordHasher,
+    PasswordPolicyConfig, TenantContext,
+};
+use serde::{Deserialize, Serialize};
+
+const SPECIAL_CHARACTERS: &str = "!@#$%^&*()_+-=[]{}|;:,.<>?";
+
+/// Effective password rules for one tenant
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    pub require_complexity: bool,
+    /// Previous passwords that may not be reused, not counting the current one
+    pub history_count: u32,
+}
+
+impl From<&PasswordPolicyConfig> for PasswordPolicy {
+    fn from(config: &PasswordPolicyConfig) -> Self {
+        Self {
+            min_length: config.min_length,
+            require_complexity: config.require_complexity,
+            history_count: config.history_count,
+        }
+    }
+}
+
+impl PasswordPolicy {
+    /// The tenant's own policy, or `defaults` when the tenant has none
+    pub async fn for_tenant(
+        repository: &AuthRepository,
+        tenant: &TenantContext,
+        defaults: &PasswordPolicy,
+    ) -> Result<Self> {
+        Ok(repository
+            .get_tenant_password_policy(tenant)
+            .await?
+            .unwrap_or_else(|| defaults.clone()))
+    }
+
+    /// Every rule the password breaks, in a user-presentable form
+    pub fn violations(&self, password: &str) -> Vec<String> {
+        let mut violations = Vec::new();
+
+        if password.chars().count() < self.min_length {
+            violations.push(format!("Password must be at least {} characters long", self.min_length));
+        }
+
+        if self.require_complexity {
+            if !password.chars().any(|c| c.is_uppercase()) {
+                violations.push("Password must contain at least one uppercase letter".to_string());
+            }
+            if !password.chars().any(|c| c.is_lowercase()) {
+                violations.push("Password must contain at least one lowercase letter".to_string());
+            }
+            if !password.chars().any(|c| c.is_numeric()) {
+                violations.push("Password must contain at least one number".to_string());
+            }
+            if !password.chars().any(|c| SPECIAL_CHARACTERS.contains(c)) {
+                violations.push("Password must contain at least one special character".to_string());
+            }
+        }
+
+        violations
+    }
+
+    pub fn validate(&self, password: &str) -> Result<()> {
+        let violations = self.violations(password);
+        if violations.is_empty() {
+            return Ok(());
+        }
+
+        Err(Error::new(ErrorCode::ValidationFailed, "Password does not meet the password policy")
+            .with_details(violations.join("; ")))
+    }
+
+    /// Reject a password matching the current hash or one of the last
+    /// `history_count` previous hashes (newest first)
+    pub fn check_history(
+        &self,
+        hasher: &PasswordHasher,
+        password: &str,
+        current_hash: Option<&str>,
+        previous_hashes: &[String],
+    ) -> Result<()> {
+        let recent = previous_hashes.iter().take(self.history_count as usize).map(String::as_str);
+
+        for hash in current_hash.into_iter().chain(recent) {
+            if hasher.verify_password(password, hash)? {
+                return Err(Error::new(
+                    ErrorCode::ValidationFailed,
+                    "Password was used recently and cannot be reused",
+                )
+                .with_details(format!(
+                    "Choose a password different from your last {} passwords",
+                    self.history_count + 1
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use erp_core::config::SecurityConfig;
+
+    fn hasher() -> PasswordHasher {
+        PasswordHasher::new(&SecurityConfig {
+            argon2_memory_cost: 1024,
+            argon2_time_cost: 1,
+            argon2_parallelism: 1,
+            aes_encryption_key: "test-encryption-key-exactly-32-ch".to_string(),
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_policy_violations_are_listed() {
+        let policy = PasswordPolicy { min_length: 12, require_complexity: true, history_count: 3 };
+
+        let error = policy.validate("short1!").unwrap_err();
+        assert_eq!(error.code, ErrorCode::ValidationFailed);
+        let details = error.details.unwrap();
+        assert!(details.contains("at least 12 characters"));
+        assert!(details.contains("uppercase"));
+        assert!(!details.contains("special character"));
+
+        assert!(policy.validate("Longer-Passw0rd").is_ok());
+        let relaxed = PasswordPolicy { require_complexity: false, ..policy };
+        assert!(relaxed.validate("all lowercase words").is_ok());
+    }
+
+    #[test]
+    fn test_history_rejects_recent_passwords_only() {
+        let hasher = hasher();
+        let policy = PasswordPolicy { min_length: 8, require_complexity: true, history_count: 2 };
+        let current = hasher.hash_password("Current-Passw0rd").unwrap();
+        let previous: Vec<String> = ["Newest-Passw0rd", "Older-Passw0rd", "Oldest-Passw0rd"]
+            .iter()
+            .map(|p| hasher.hash_password(p).unwrap())
+            .collect();
+
+        let reused = |password: &str| policy.check_history(&hasher, password, Some(&current), &previous).is_err();
+        assert!(reused("Current-Passw0rd"));
+        assert!(reused("Newest-Passw0rd"));
+        assert!(reused("Older-Passw0rd"));
+        // Beyond the history window
+        assert!(!reused("Oldest-Passw0rd"));
+        assert!(!reused("Brand-New-Passw0rd"));
+    }
+}
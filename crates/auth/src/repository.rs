@@ -1,6 +1,8 @@
-use crate::models::{Permission, Role, Tenant, User};
+use crate::models::{AccountState, Permission, PortalAccount, Role, Tenant, User};
+use crate::password_policy::PasswordPolicy;
+use crate::role_catalog::{PermissionQuery, RoleGrant, RoleQuery};
 use chrono::{DateTime, Utc};
-use erp_core::{DatabasePool, Error, Result, TenantContext};
+use erp_core::{DatabasePool, Error, Result, SessionLimit, SessionLimitStrategy, TenantContext};
 use sqlx::Row;
 use uuid::Uuid;
 
@@ -304,20 +306,96 @@ impl AuthRepository {
         .execute(pool.get())
         .await?;
 
+        sqlx::query("DELETE FROM public.two_factor_backup_codes WHERE tenant_id = $1 AND user_id = $2")
+            .bind(tenant.tenant_id.0)
+            .bind(user_id)
+            .execute(&self.db.main_pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Replaces the user's backup codes with the given hashes.
+    pub async fn replace_backup_codes(
+        &self,
+        tenant: &TenantContext,
+        user_id: Uuid,
+        code_hashes: &[String],
+    ) -> Result<()> {
+        let mut tx = self.db.main_pool.begin().await?;
+
+        sqlx::query("DELETE FROM public.two_factor_backup_codes WHERE tenant_id = $1 AND user_id = $2")
+            .bind(tenant.tenant_id.0)
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query(
+            "INSERT INTO public.two_factor_backup_codes (tenant_id, user_id, code_hash)
+             SELECT $1, $2, UNNEST($3::TEXT[])"
+        )
+        .bind(tenant.tenant_id.0)
+        .bind(user_id)
+        .bind(code_hashes)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
         Ok(())
     }
 
+    /// Gets the ids and hashes of the user's unused backup codes.
+    pub async fn get_unused_backup_codes(
+        &self,
+        tenant: &TenantContext,
+        user_id: Uuid,
+    ) -> Result<Vec<(i64, String)>> {
+        let codes = sqlx::query_as(
+            "SELECT id, code_hash FROM public.two_factor_backup_codes
+             WHERE tenant_id = $1 AND user_id = $2 AND used_at IS NULL
+             ORDER BY id"
+        )
+        .bind(tenant.tenant_id.0)
+        .bind(user_id)
+        .fetch_all(&self.db.main_pool)
+        .await?;
+
+        Ok(codes)
+    }
+
+    /// Marks a backup code used; false when it already was, e.g. by a concurrent sign-in.
+    pub async fn consume_backup_code(
+        &self,
+        tenant: &TenantContext,
+        user_id: Uuid,
+        code_id: i64,
+    ) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE public.two_factor_backup_codes SET used_at = NOW()
+             WHERE id = $1 AND tenant_id = $2 AND user_id = $3 AND used_at IS NULL"
+        )
+        .bind(code_id)
+        .bind(tenant.tenant_id.0)
+        .bind(user_id)
+        .execute(&self.db.main_pool)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
     pub async fn list_users(
         &self,
         tenant: &TenantContext,
+        state: Option<AccountState>,
         limit: i64,
         offset: i64,
     ) -> Result<Vec<User>> {
         let pool = self.db.get_tenant_pool(tenant).await?;
         
-        let users = sqlx::query_as::<_, User>(
-            "SELECT * FROM users ORDER BY created_at DESC LIMIT $1 OFFSET $2"
-        )
+        let users = sqlx::query_as::<_, User>(&format!(
+            "SELECT * FROM users WHERE {} ORDER BY created_at DESC LIMIT $1 OFFSET $2",
+            state.map_or("TRUE", state_condition)
+        ))
         .bind(limit)
         .bind(offset)
         .fetch_all(pool.get())
@@ -326,12 +404,39 @@ impl AuthRepository {
         Ok(users)
     }
 
-    pub async fn count_users(&self, tenant: &TenantContext) -> Result<i64> {
+    /// Users holding a seat: active or locked, not deleted
+    pub async fn count_seated_users(&self, tenant: &TenantContext) -> Result<i64> {
         let pool = self.db.get_tenant_pool(tenant).await?;
-        
+
         let count = sqlx::query_scalar::<_, i64>(
-            "SELECT COUNT(*) FROM users"
+            "SELECT COUNT(*) FROM users WHERE deleted_at IS NULL AND is_active"
+        )
+        .fetch_one(pool.get())
+        .await?;
+
+        Ok(count)
+    }
+
+    /// The tenant's seat limit, `None` for unlimited
+    pub async fn get_seat_limit(&self, tenant_id: Uuid) -> Result<Option<i32>> {
+        let limit = sqlx::query_scalar::<_, Option<i32>>(
+            "SELECT seat_limit FROM public.tenants WHERE id = $1"
         )
+        .bind(tenant_id)
+        .fetch_optional(&self.db.main_pool)
+        .await?;
+
+        Ok(limit.flatten())
+    }
+
+    /// Users matched by [`Self::list_users`] across all pages
+    pub async fn count_users(&self, tenant: &TenantContext, state: Option<AccountState>) -> Result<i64> {
+        let pool = self.db.get_tenant_pool(tenant).await?;
+        
+        let count = sqlx::query_scalar::<_, i64>(&format!(
+            "SELECT COUNT(*) FROM users WHERE {}",
+            state.map_or("TRUE", state_condition)
+        ))
         .fetch_one(pool.get())
         .await?;
 
@@ -487,6 +592,52 @@ impl AuthRepository {
         Ok(())
     }
 
+    /// Makes a deactivated or soft-deleted user active again.
+    pub async fn reactivate_user(
+        &self,
+        tenant: &TenantContext,
+        user_id: Uuid,
+    ) -> Result<User> {
+        let pool = self.db.get_tenant_pool(tenant).await?;
+
+        let user = sqlx::query_as::<_, User>(
+            "UPDATE users SET is_active = true, deleted_at = NULL, updated_at = CURRENT_TIMESTAMP
+             WHERE id = $1 AND (deleted_at IS NOT NULL OR NOT is_active)
+             RETURNING *"
+        )
+        .bind(user_id)
+        .fetch_optional(pool.get())
+        .await?;
+
+        user.ok_or_else(|| Error::new(erp_core::ErrorCode::ConflictError, "User is neither deactivated nor deleted"))
+    }
+
+    /// Sets the first password of an invited user, verifies their email and
+    /// makes them active; `None` if the user has a password already.
+    pub async fn accept_invitation(
+        &self,
+        tenant: &TenantContext,
+        user_id: Uuid,
+        password_hash: &str,
+    ) -> Result<Option<User>> {
+        let pool = self.db.get_tenant_pool(tenant).await?;
+
+        let user = sqlx::query_as::<_, User>(
+            "UPDATE users
+             SET password_hash = $1, password_changed_at = NOW(),
+                 email_verified_at = COALESCE(email_verified_at, NOW()),
+                 is_active = true, updated_at = CURRENT_TIMESTAMP
+             WHERE id = $2 AND password_hash IS NULL AND deleted_at IS NULL
+             RETURNING *"
+        )
+        .bind(password_hash)
+        .bind(user_id)
+        .fetch_optional(pool.get())
+        .await?;
+
+        Ok(user)
+    }
+
     /// Gets all users assigned to a specific role.
     pub async fn get_users_with_role(
         &self,
@@ -652,6 +803,104 @@ impl AuthRepository {
         self.get_all_permissions(tenant).await
     }
 
+    /// A page of the roles matching the query, and how many match in total.
+    pub async fn search_roles(&self, tenant: &TenantContext, query: &RoleQuery) -> Result<(Vec<Role>, i64)> {
+        let pool = self.db.get_tenant_pool(tenant).await?;
+        let pattern = query.search.as_ref().map(|term| term.role_pattern());
+        let condition = "$1::TEXT IS NULL OR lower(name || ' ' || coalesce(description, '')) LIKE $1";
+
+        let total: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM roles WHERE {}", condition))
+            .bind(&pattern)
+            .fetch_one(pool.get())
+            .await?;
+        let roles = sqlx::query_as::<_, Role>(&format!(
+            "SELECT * FROM roles WHERE {} ORDER BY {} LIMIT $2 OFFSET $3",
+            condition,
+            query.sort.order_by(query.descending)
+        ))
+        .bind(&pattern)
+        .bind(query.page.limit as i64)
+        .bind(query.page.offset())
+        .fetch_all(pool.get())
+        .await?;
+
+        Ok((roles, total))
+    }
+
+    /// A page of the permissions matching the query, and how many match in total.
+    pub async fn search_permissions(&self, tenant: &TenantContext, query: &PermissionQuery) -> Result<(Vec<Permission>, i64)> {
+        let pool = self.db.get_tenant_pool(tenant).await?;
+        let search = query.search.as_ref();
+        // The first condition is the indexed one, the others make the colon form exact
+        let condition = "($1::TEXT IS NULL OR lower(resource || ':' || action || ' ' || coalesce(description, '')) LIKE $1) \
+             AND ($2::TEXT IS NULL OR lower(resource) LIKE $2) \
+             AND ($3::TEXT IS NULL OR lower(action) LIKE $3) \
+             AND ($4::TEXT IS NULL OR resource = $4)";
+
+        let total: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM permissions WHERE {}", condition))
+            .bind(search.map(|term| term.text_pattern()))
+            .bind(search.and_then(|term| term.resource_pattern()))
+            .bind(search.and_then(|term| term.action_pattern()))
+            .bind(&query.resource)
+            .fetch_one(pool.get())
+            .await?;
+        let permissions = sqlx::query_as::<_, Permission>(&format!(
+            "SELECT * FROM permissions WHERE {} ORDER BY {} LIMIT $5 OFFSET $6",
+            condition,
+            query.sort.order_by(query.descending)
+        ))
+        .bind(search.map(|term| term.text_pattern()))
+        .bind(search.and_then(|term| term.resource_pattern()))
+        .bind(search.and_then(|term| term.action_pattern()))
+        .bind(&query.resource)
+        .bind(query.page.limit as i64)
+        .bind(query.page.offset())
+        .fetch_all(pool.get())
+        .await?;
+
+        Ok((permissions, total))
+    }
+
+    /// Gets the permissions a role grants.
+    pub async fn get_role_permissions(&self, tenant: &TenantContext, role_id: Uuid) -> Result<Vec<Permission>> {
+        let pool = self.db.get_tenant_pool(tenant).await?;
+
+        let permissions = sqlx::query_as::<_, Permission>(
+            "SELECT p.* FROM permissions p
+             INNER JOIN role_permissions rp ON p.id = rp.permission_id
+             WHERE rp.role_id = $1
+             ORDER BY p.resource, p.action"
+        )
+        .bind(role_id)
+        .fetch_all(pool.get())
+        .await?;
+
+        Ok(permissions)
+    }
+
+    /// Gets which role grants which permission, optionally of one permission only.
+    pub async fn get_role_grants(&self, tenant: &TenantContext, permission_id: Option<Uuid>) -> Result<Vec<RoleGrant>> {
+        let pool = self.db.get_tenant_pool(tenant).await?;
+
+        let rows = sqlx::query(
+            "SELECT rp.role_id, r.name AS role_name, rp.permission_id FROM role_permissions rp
+             INNER JOIN roles r ON r.id = rp.role_id
+             WHERE $1::UUID IS NULL OR rp.permission_id = $1"
+        )
+        .bind(permission_id)
+        .fetch_all(pool.get())
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| RoleGrant {
+                role_id: row.get("role_id"),
+                role_name: row.get("role_name"),
+                permission_id: row.get("permission_id"),
+            })
+            .collect())
+    }
+
     /// Gets a permission by ID.
     pub async fn get_permission_by_id(
         &self,
@@ -740,7 +989,279 @@ impl AuthRepository {
 
         Ok(result.unwrap_or(false))
     }
+
+    // Password Policy Repository Methods
+
+    /// Gets the tenant's own password policy, if it has one.
+    pub async fn get_tenant_password_policy(
+        &self,
+        tenant: &TenantContext,
+    ) -> Result<Option<PasswordPolicy>> {
+        let row = sqlx::query(
+            "SELECT min_length, require_complexity, history_count
+             FROM public.tenant_password_policies WHERE tenant_id = $1"
+        )
+        .bind(tenant.tenant_id.0)
+        .fetch_optional(&self.db.main_pool)
+        .await?;
+
+        row.map(|row| -> Result<PasswordPolicy> {
+            Ok(PasswordPolicy {
+                min_length: row.try_get::<i32, _>("min_length")?.max(0) as usize,
+                require_complexity: row.try_get("require_complexity")?,
+                history_count: row.try_get::<i32, _>("history_count")?.max(0) as u32,
+            })
+        })
+        .transpose()
+    }
+
+    // Session Limit Repository Methods
+
+    /// Gets the tenant's own session limit, if it has one.
+    pub async fn get_tenant_session_limit(&self, tenant: &TenantContext) -> Result<Option<SessionLimit>> {
+        let row = sqlx::query(
+            "SELECT max_sessions_per_user, limit_strategy, sliding_sessions
+             FROM public.tenant_session_limits WHERE tenant_id = $1"
+        )
+        .bind(tenant.tenant_id.0)
+        .fetch_optional(&self.db.main_pool)
+        .await?;
+
+        row.map(|row| -> Result<SessionLimit> {
+            let strategy: String = row.try_get("limit_strategy")?;
+            Ok(SessionLimit {
+                max_sessions_per_user: row.try_get::<i32, _>("max_sessions_per_user")?.max(1) as u32,
+                strategy: SessionLimitStrategy::parse(&strategy).unwrap_or_default(),
+                sliding_sessions: row.try_get("sliding_sessions")?,
+            })
+        })
+        .transpose()
+    }
+
+    /// Whether the tenant has turned on sliding sessions.
+    pub async fn tenant_sliding_sessions(&self, tenant_id: Uuid) -> Result<bool> {
+        let enabled: Option<bool> = sqlx::query_scalar(
+            "SELECT sliding_sessions FROM public.tenant_session_limits WHERE tenant_id = $1"
+        )
+        .bind(tenant_id)
+        .fetch_optional(&self.db.main_pool)
+        .await?;
+
+        Ok(enabled.unwrap_or(false))
+    }
+
+    /// Stores the tenant's session limit, replacing the one it had.
+    pub async fn set_tenant_session_limit(
+        &self,
+        tenant: &TenantContext,
+        limit: &SessionLimit,
+        updated_by: Uuid,
+    ) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO public.tenant_session_limits (tenant_id, max_sessions_per_user, limit_strategy, sliding_sessions, updated_by)
+             VALUES ($1, $2, $3, $4, $5)
+             ON CONFLICT (tenant_id) DO UPDATE
+             SET max_sessions_per_user = EXCLUDED.max_sessions_per_user,
+                 limit_strategy = EXCLUDED.limit_strategy,
+                 sliding_sessions = EXCLUDED.sliding_sessions,
+                 updated_by = EXCLUDED.updated_by,
+                 updated_at = NOW()"
+        )
+        .bind(tenant.tenant_id.0)
+        .bind(limit.max_sessions_per_user as i32)
+        .bind(limit.strategy.as_str())
+        .bind(limit.sliding_sessions)
+        .bind(updated_by)
+        .execute(&self.db.main_pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Gets the user's previous password hashes, newest first.
+    pub async fn get_password_history(
+        &self,
+        tenant: &TenantContext,
+        user_id: Uuid,
+        limit: u32,
+    ) -> Result<Vec<String>> {
+        if limit == 0 {
+            return Ok(Vec::new());
+        }
+
+        let hashes = sqlx::query_scalar(
+            "SELECT password_hash FROM public.password_history
+             WHERE tenant_id = $1 AND user_id = $2
+             ORDER BY created_at DESC, id DESC
+             LIMIT $3"
+        )
+        .bind(tenant.tenant_id.0)
+        .bind(user_id)
+        .bind(i64::from(limit))
+        .fetch_all(&self.db.main_pool)
+        .await?;
+
+        Ok(hashes)
+    }
+
+    /// Records a replaced password hash and keeps only the newest `keep` entries.
+    pub async fn record_password_history(
+        &self,
+        tenant: &TenantContext,
+        user_id: Uuid,
+        password_hash: &str,
+        keep: u32,
+    ) -> Result<()> {
+        let mut tx = self.db.main_pool.begin().await?;
+
+        sqlx::query(
+            "INSERT INTO public.password_history (tenant_id, user_id, password_hash)
+             VALUES ($1, $2, $3)"
+        )
+        .bind(tenant.tenant_id.0)
+        .bind(user_id)
+        .bind(password_hash)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            "DELETE FROM public.password_history
+             WHERE tenant_id = $1 AND user_id = $2 AND id NOT IN (
+                 SELECT id FROM public.password_history
+                 WHERE tenant_id = $1 AND user_id = $2
+                 ORDER BY created_at DESC, id DESC
+                 LIMIT $3
+             )"
+        )
+        .bind(tenant.tenant_id.0)
+        .bind(user_id)
+        .bind(i64::from(keep))
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    // Customer Portal Accounts
+
+    pub async fn create_portal_account(
+        &self,
+        tenant: &TenantContext,
+        user_id: Uuid,
+        request: &crate::dto::InvitePortalUserRequest,
+        invited_by: Option<Uuid>,
+    ) -> Result<PortalAccount> {
+        let account = sqlx::query_as::<_, PortalAccount>(
+            "INSERT INTO public.customer_portal_users
+                 (tenant_id, user_id, customer_id, contact_id, email, include_children, invited_by)
+             VALUES ($1, $2, $3, $4, $5, $6, $7)
+             RETURNING *"
+        )
+        .bind(tenant.tenant_id.0)
+        .bind(user_id)
+        .bind(request.customer_id)
+        .bind(request.contact_id)
+        .bind(&request.email)
+        .bind(request.include_children)
+        .bind(invited_by)
+        .fetch_one(&self.db.main_pool)
+        .await?;
+
+        Ok(account)
+    }
+
+    /// The portal account of a user, whatever its status; `None` for tenant users
+    pub async fn get_portal_account(
+        &self,
+        tenant: &TenantContext,
+        user_id: Uuid,
+    ) -> Result<Option<PortalAccount>> {
+        let account = sqlx::query_as::<_, PortalAccount>(
+            "SELECT * FROM public.customer_portal_users WHERE tenant_id = $1 AND user_id = $2"
+        )
+        .bind(tenant.tenant_id.0)
+        .bind(user_id)
+        .fetch_optional(&self.db.main_pool)
+        .await?;
+
+        Ok(account)
+    }
+
+    pub async fn list_portal_accounts(
+        &self,
+        tenant: &TenantContext,
+        customer_id: Uuid,
+    ) -> Result<Vec<PortalAccount>> {
+        let accounts = sqlx::query_as::<_, PortalAccount>(
+            "SELECT * FROM public.customer_portal_users
+             WHERE tenant_id = $1 AND customer_id = $2
+             ORDER BY invited_at DESC"
+        )
+        .bind(tenant.tenant_id.0)
+        .bind(customer_id)
+        .fetch_all(&self.db.main_pool)
+        .await?;
+
+        Ok(accounts)
+    }
+
+    /// Moves an invited account to `active`; `None` unless it was invited
+    pub async fn activate_portal_account(
+        &self,
+        tenant: &TenantContext,
+        user_id: Uuid,
+    ) -> Result<Option<PortalAccount>> {
+        let account = sqlx::query_as::<_, PortalAccount>(
+            "UPDATE public.customer_portal_users SET status = 'active', accepted_at = $3
+             WHERE tenant_id = $1 AND user_id = $2 AND status = 'invited'
+             RETURNING *"
+        )
+        .bind(tenant.tenant_id.0)
+        .bind(user_id)
+        .bind(Utc::now())
+        .fetch_optional(&self.db.main_pool)
+        .await?;
+
+        Ok(account)
+    }
+
+    /// Marks an account of the customer revoked; `None` if there is none or it already was
+    pub async fn revoke_portal_account(
+        &self,
+        tenant: &TenantContext,
+        customer_id: Uuid,
+        user_id: Uuid,
+        revoked_by: Option<Uuid>,
+    ) -> Result<Option<PortalAccount>> {
+        let account = sqlx::query_as::<_, PortalAccount>(
+            "UPDATE public.customer_portal_users SET status = 'revoked', revoked_by = $4, revoked_at = $5
+             WHERE tenant_id = $1 AND customer_id = $2 AND user_id = $3 AND status <> 'revoked'
+             RETURNING *"
+        )
+        .bind(tenant.tenant_id.0)
+        .bind(customer_id)
+        .bind(user_id)
+        .bind(revoked_by)
+        .bind(Utc::now())
+        .fetch_optional(&self.db.main_pool)
+        .await?;
+
+        Ok(account)
+    }
 }
 
 // Type alias for workflow compatibility
-pub type UserRepository = AuthRepository;
\ No newline at end of file
+pub type UserRepository = AuthRepository;
+
+/// `WHERE` condition selecting the users in `state`, see [`User::state`]
+fn state_condition(state: AccountState) -> &'static str {
+    match state {
+        AccountState::Active => {
+            "deleted_at IS NULL AND is_active AND (locked_until IS NULL OR locked_until <= NOW())"
+        }
+        AccountState::Deactivated => "deleted_at IS NULL AND NOT is_active",
+        AccountState::SoftDeleted => "deleted_at IS NOT NULL",
+        AccountState::Locked => "deleted_at IS NULL AND is_active AND locked_until > NOW()",
+    }
+}
@@ -0,0 +1,458 @@
+//! # Role and Permission Catalog
+//!
+//! Search, sorting and paging of the role and permission listings used by
+//! the role editor, and the views built on the role grants: a role expanded
+//! into its permissions with the other roles granting each one, and the
+//! roles granting a permission.
+//!
+//! A search term matches permissions on `resource`, `action` and
+//! description. Written as `resource:action` it matches both parts
+//! separately, so `inventory:` finds every inventory permission and `:read`
+//! every read permission, whatever their descriptions say. Roles match on
+//! name and description. The repository runs the same conditions in SQL,
+//! backed by trigram indexes; [`SearchTerm::matches_permission`] and
+//! [`SearchTerm::matches_role`] state them in Rust.
+
+use crate::dto::{PermissionResponse, RoleResponse};
+use crate::models::{Permission, Role};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Largest page the listings return
+pub const MAX_PAGE_SIZE: u32 = 200;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RoleSort {
+    #[default]
+    Name,
+    CreatedAt,
+}
+
+impl RoleSort {
+    pub(crate) fn order_by(&self, descending: bool) -> &'static str {
+        match (self, descending) {
+            (RoleSort::Name, false) => "name ASC, id",
+            (RoleSort::Name, true) => "name DESC, id",
+            (RoleSort::CreatedAt, false) => "created_at ASC, name",
+            (RoleSort::CreatedAt, true) => "created_at DESC, name",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PermissionSort {
+    /// By resource, then action
+    #[default]
+    Resource,
+    /// By action, then resource
+    Action,
+}
+
+impl PermissionSort {
+    pub(crate) fn order_by(&self, descending: bool) -> &'static str {
+        match (self, descending) {
+            (PermissionSort::Resource, false) => "resource ASC, action ASC",
+            (PermissionSort::Resource, true) => "resource DESC, action DESC",
+            (PermissionSort::Action, false) => "action ASC, resource ASC",
+            (PermissionSort::Action, true) => "action DESC, resource DESC",
+        }
+    }
+}
+
+/// Query of the role listing
+#[derive(Debug, Clone, Default)]
+pub struct RoleQuery {
+    pub search: Option<SearchTerm>,
+    pub sort: RoleSort,
+    pub descending: bool,
+    pub page: Page,
+}
+
+/// Query of the permission listing
+#[derive(Debug, Clone, Default)]
+pub struct PermissionQuery {
+    pub search: Option<SearchTerm>,
+    /// Only permissions of this resource, matched exactly
+    pub resource: Option<String>,
+    pub sort: PermissionSort,
+    pub descending: bool,
+    pub page: Page,
+}
+
+/// A 1-based page of a listing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Page {
+    pub page: u32,
+    pub limit: u32,
+}
+
+impl Default for Page {
+    fn default() -> Self {
+        Self { page: 1, limit: 50 }
+    }
+}
+
+impl Page {
+    /// Page 0 is read as the first page, the limit is capped at [`MAX_PAGE_SIZE`]
+    pub fn new(page: u32, limit: u32) -> Self {
+        Self {
+            page: page.max(1),
+            limit: limit.clamp(1, MAX_PAGE_SIZE),
+        }
+    }
+
+    pub fn offset(&self) -> i64 {
+        (self.page as i64 - 1) * self.limit as i64
+    }
+
+    pub fn total_pages(&self, total: i64) -> i64 {
+        (total + self.limit as i64 - 1) / self.limit as i64
+    }
+
+    pub fn pagination(&self, total: i64) -> Pagination {
+        Pagination {
+            page: self.page,
+            limit: self.limit,
+            total,
+            total_pages: self.total_pages(total),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Pagination {
+    pub page: u32,
+    pub limit: u32,
+    /// Matches across all pages
+    pub total: i64,
+    pub total_pages: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RoleListing {
+    pub roles: Vec<RoleResponse>,
+    pub pagination: Pagination,
+}
+
+/// A page of permissions, grouped by resource for the role editor
+#[derive(Debug, Serialize)]
+pub struct PermissionListing {
+    pub groups: Vec<ResourceGroup<PermissionResponse>>,
+    pub pagination: Pagination,
+}
+
+/// A free-text search term, lowercased
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchTerm {
+    /// Anywhere in `resource:action description`, or in a role's name and description
+    pub text: String,
+    /// With `resource:action`, the part before the colon
+    pub resource: Option<String>,
+    /// With `resource:action`, the part after the colon
+    pub action: Option<String>,
+}
+
+fn like_contains(value: &str) -> String {
+    let escaped = value.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+    format!("%{}%", escaped)
+}
+
+impl SearchTerm {
+    /// `None` for a blank term
+    pub fn parse(term: &str) -> Option<Self> {
+        let term = term.trim().to_lowercase();
+        if term.is_empty() {
+            return None;
+        }
+        let Some((resource, action)) = term.split_once(':') else {
+            return Some(Self { text: term, resource: None, action: None });
+        };
+        let part = |value: &str| Some(value.trim().to_string()).filter(|value| !value.is_empty());
+        Some(Self {
+            resource: part(resource),
+            action: part(action),
+            text: term,
+        })
+    }
+
+    fn is_qualified(&self) -> bool {
+        self.resource.is_some() || self.action.is_some()
+    }
+
+    /// LIKE pattern over the indexed `resource:action description` text
+    pub(crate) fn text_pattern(&self) -> String {
+        if !self.is_qualified() {
+            return like_contains(&self.text);
+        }
+        format!(
+            "{}:{}",
+            self.resource.as_deref().map(like_contains).unwrap_or_else(|| "%".to_string()),
+            self.action.as_deref().map(like_contains).unwrap_or_else(|| "%".to_string()),
+        )
+    }
+
+    pub(crate) fn resource_pattern(&self) -> Option<String> {
+        self.resource.as_deref().map(like_contains)
+    }
+
+    pub(crate) fn action_pattern(&self) -> Option<String> {
+        self.action.as_deref().map(like_contains)
+    }
+
+    /// Pattern over a role's name and description; the colon form has no meaning for roles
+    pub(crate) fn role_pattern(&self) -> String {
+        like_contains(&self.text)
+    }
+
+    pub fn matches_permission(&self, permission: &Permission) -> bool {
+        let resource = permission.resource.to_lowercase();
+        let action = permission.action.to_lowercase();
+        if self.is_qualified() {
+            return self.resource.as_ref().is_none_or(|part| resource.contains(part.as_str()))
+                && self.action.as_ref().is_none_or(|part| action.contains(part.as_str()));
+        }
+        let description = permission.description.as_deref().unwrap_or_default().to_lowercase();
+        format!("{}:{} {}", resource, action, description).contains(&self.text)
+    }
+
+    pub fn matches_role(&self, role: &Role) -> bool {
+        let description = role.description.as_deref().unwrap_or_default();
+        format!("{} {}", role.name, description).to_lowercase().contains(&self.text)
+    }
+}
+
+/// Something listed under its resource
+pub trait HasResource {
+    fn resource(&self) -> &str;
+}
+
+impl HasResource for Permission {
+    fn resource(&self) -> &str {
+        &self.resource
+    }
+}
+
+impl HasResource for PermissionResponse {
+    fn resource(&self) -> &str {
+        &self.resource
+    }
+}
+
+/// Permissions of one resource
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ResourceGroup<T> {
+    pub resource: String,
+    pub permissions: Vec<T>,
+}
+
+/// Group by resource. Groups follow the first appearance of their resource and
+/// keep the order of the listing within, so the requested sort survives and
+/// the same listing always groups the same way.
+pub fn group_by_resource<T: HasResource>(items: Vec<T>) -> Vec<ResourceGroup<T>> {
+    let mut groups: Vec<ResourceGroup<T>> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+    for item in items {
+        let position = *index.entry(item.resource().to_string()).or_insert_with(|| {
+            groups.push(ResourceGroup {
+                resource: item.resource().to_string(),
+                permissions: Vec::new(),
+            });
+            groups.len() - 1
+        });
+        groups[position].permissions.push(item);
+    }
+    groups
+}
+
+/// A role granting a permission
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoleGrant {
+    pub role_id: Uuid,
+    pub role_name: String,
+    pub permission_id: Uuid,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RoleRef {
+    pub id: Uuid,
+    pub name: String,
+}
+
+/// The roles granting the permission, by name
+pub fn roles_granting(permission_id: Uuid, grants: &[RoleGrant]) -> Vec<RoleRef> {
+    let mut roles: Vec<RoleRef> = grants
+        .iter()
+        .filter(|grant| grant.permission_id == permission_id)
+        .map(|grant| RoleRef {
+            id: grant.role_id,
+            name: grant.role_name.clone(),
+        })
+        .collect();
+    roles.sort_by(|a, b| a.name.cmp(&b.name).then(a.id.cmp(&b.id)));
+    roles.dedup();
+    roles
+}
+
+/// A permission of a role, with the other roles that grant it too
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EffectivePermission {
+    pub id: Uuid,
+    pub resource: String,
+    pub action: String,
+    pub description: Option<String>,
+    pub also_granted_by: Vec<RoleRef>,
+    /// Last day anyone in the tenant used it; `None` without usage tracking
+    pub last_used: Option<NaiveDate>,
+    /// Used within the usage window; `None` without usage tracking
+    pub recently_used: Option<bool>,
+}
+
+impl HasResource for EffectivePermission {
+    fn resource(&self) -> &str {
+        &self.resource
+    }
+}
+
+impl EffectivePermission {
+    /// The permission as `require_permission` checks and usage tracking records it
+    pub fn key(&self) -> String {
+        format!("{}:{}", self.resource, self.action)
+    }
+}
+
+/// The role's permissions, by resource and action
+pub fn effective_permissions(role_id: Uuid, permissions: Vec<Permission>, grants: &[RoleGrant]) -> Vec<EffectivePermission> {
+    let mut expanded: Vec<EffectivePermission> = permissions
+        .into_iter()
+        .map(|permission| EffectivePermission {
+            also_granted_by: roles_granting(permission.id, grants)
+                .into_iter()
+                .filter(|role| role.id != role_id)
+                .collect(),
+            id: permission.id,
+            resource: permission.resource,
+            action: permission.action,
+            description: permission.description,
+            last_used: None,
+            recently_used: None,
+        })
+        .collect();
+    expanded.sort_by(|a, b| a.resource.cmp(&b.resource).then(a.action.cmp(&b.action)));
+    expanded
+}
+
+/// Fill in usage from the last day each permission was used
+pub fn with_usage(permissions: &mut [EffectivePermission], last_used: &HashMap<String, NaiveDate>, since: NaiveDate) {
+    for permission in permissions {
+        permission.last_used = last_used.get(&permission.key()).copied();
+        permission.recently_used = Some(permission.last_used.is_some_and(|day| day >= since));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn permission(resource: &str, action: &str, description: &str) -> Permission {
+        Permission {
+            id: Uuid::new_v4(),
+            resource: resource.to_string(),
+            action: action.to_string(),
+            description: Some(description.to_string()),
+        }
+    }
+
+    fn grant(role_id: Uuid, role_name: &str, permission: &Permission) -> RoleGrant {
+        RoleGrant {
+            role_id,
+            role_name: role_name.to_string(),
+            permission_id: permission.id,
+        }
+    }
+
+    #[test]
+    fn test_search_matches_action_or_resource_part() {
+        let inventory_read = permission("inventory", "read", "View stock levels");
+        let reports_read = permission("reports", "read", "Read inventory reports");
+        let inventory_write = permission("inventory", "write", "Change stock");
+
+        let free = SearchTerm::parse(" Read ").unwrap();
+        assert!(free.matches_permission(&inventory_read) && free.matches_permission(&reports_read));
+        assert!(!free.matches_permission(&inventory_write));
+
+        // Free text also finds the description, the resource part does not
+        let free = SearchTerm::parse("inventory").unwrap();
+        assert!(free.matches_permission(&reports_read));
+        let resource = SearchTerm::parse("inventory:").unwrap();
+        assert_eq!(resource.action, None);
+        assert!(resource.matches_permission(&inventory_read) && resource.matches_permission(&inventory_write));
+        assert!(!resource.matches_permission(&reports_read));
+
+        let action = SearchTerm::parse(":read").unwrap();
+        assert!(action.matches_permission(&inventory_read) && action.matches_permission(&reports_read));
+        assert!(!action.matches_permission(&inventory_write));
+
+        let both = SearchTerm::parse("inv:wr").unwrap();
+        assert_eq!(both.text_pattern(), "%inv%:%wr%");
+        assert!(both.matches_permission(&inventory_write) && !both.matches_permission(&inventory_read));
+
+        assert_eq!(SearchTerm::parse("100%_off").unwrap().text_pattern(), "%100\\%\\_off%");
+        assert!(SearchTerm::parse("   ").is_none());
+    }
+
+    #[test]
+    fn test_reverse_lookup_lists_each_granting_role_once() {
+        let (admin, clerk, auditor) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let read = permission("inventory", "read", "");
+        let write = permission("inventory", "write", "");
+        let grants = vec![
+            grant(clerk, "clerk", &read),
+            grant(admin, "admin", &read),
+            grant(admin, "admin", &write),
+            grant(auditor, "auditor", &read),
+        ];
+
+        let names: Vec<String> = roles_granting(read.id, &grants).into_iter().map(|role| role.name).collect();
+        assert_eq!(names, ["admin", "auditor", "clerk"]);
+        assert_eq!(roles_granting(write.id, &grants).len(), 1);
+        assert!(roles_granting(Uuid::new_v4(), &grants).is_empty());
+
+        let mut expanded = effective_permissions(admin, vec![write.clone(), read.clone()], &grants);
+        assert_eq!(expanded[0].action, "read");
+        let others: Vec<&str> = expanded[0].also_granted_by.iter().map(|role| role.name.as_str()).collect();
+        assert_eq!(others, ["auditor", "clerk"]);
+        assert!(expanded[1].also_granted_by.is_empty());
+
+        let day = NaiveDate::from_ymd_opt(2026, 3, 1).unwrap();
+        with_usage(&mut expanded, &HashMap::from([("inventory:read".to_string(), day)]), day);
+        assert_eq!((expanded[0].last_used, expanded[0].recently_used), (Some(day), Some(true)));
+        assert_eq!((expanded[1].last_used, expanded[1].recently_used), (None, Some(false)));
+    }
+
+    #[test]
+    fn test_grouping_keeps_the_listing_order() {
+        // Sorted by action, so the inventory permissions are not adjacent
+        let listing = vec![
+            permission("inventory", "read", ""),
+            permission("reports", "read", ""),
+            permission("inventory", "write", ""),
+            permission("audit", "write", ""),
+        ];
+
+        let groups = group_by_resource(listing.clone());
+        let resources: Vec<&str> = groups.iter().map(|group| group.resource.as_str()).collect();
+        assert_eq!(resources, ["inventory", "reports", "audit"]);
+        let actions: Vec<&str> = groups[0].permissions.iter().map(|p| p.action.as_str()).collect();
+        assert_eq!(actions, ["read", "write"]);
+
+        let again = group_by_resource(listing);
+        let ids = |groups: &[ResourceGroup<Permission>]| -> Vec<Uuid> {
+            groups.iter().flat_map(|group| group.permissions.iter().map(|p| p.id)).collect()
+        };
+        assert_eq!(ids(&groups), ids(&again));
+    }
+}
@@ -6,28 +6,43 @@
 
 use crate::{
     dto::*,
-    models::User,
+    models::{AccountState, PortalAccount, User},
     repository::AuthRepository,
+    password_policy::PasswordPolicy,
+    session_limit,
+    backup_codes,
+    role_catalog::{
+        self, EffectivePermission, PermissionListing, PermissionQuery, RoleListing, RoleQuery, RoleRef,
+    },
     workflows::{
-        EmailVerificationWorkflow, PasswordResetWorkflow, 
-        EmailVerificationConfig, PasswordResetConfig,
+        EmailVerificationWorkflow, PasswordResetWorkflow, PasswordChangeWorkflow, InvitationWorkflow,
+        EmailVerificationConfig, PasswordResetConfig, PasswordChangeConfig, InvitationWorkflowConfig,
+        InvitationAcceptance, InvitationDetails, InvitationSweep,
         EmailVerificationRequest, EmailVerificationConfirmation,
-        PasswordResetRequest, PasswordResetConfirmation,
+        PasswordResetRequest, PasswordResetConfirmation, PasswordChangeRequest,
     },
-    email::EmailService,
-    tokens::TokenManager,
+    email::{EmailService, EmailTemplateResolver, PostgresEmailTemplateStore},
+    tokens::{TokenManager, TokenPurpose},
 };
 use base64::{Engine, prelude::BASE64_STANDARD};
 use chrono::{Duration, Utc};
 use erp_core::{
     config::Config,
-    security::{EncryptionService, JwtService, PasswordHasher, TotpService},
+    portal::{PORTAL_PERMISSIONS, PORTAL_ROLE},
+    security::{
+        is_token_superseded, revoked_session_key, tenant_tokens_valid_after_key, token_cutoff_keys, tokens_valid_after_key, EncryptionService,
+        JwtService, PasswordHasher, TotpService,
+    },
     utils::{generate_schema_name, validate_email, validate_password},
-    DatabasePool, Error, Result, TenantContext, TenantId,
-    audit::{AuditEventBuilder, AuditLogger, DatabaseAuditRepository, EventSeverity, EventType, EventOutcome},
+    DatabasePool, Error, Result, TenantContext, TenantContextResolver, TenantId,
+    audit::{
+        AuditBackend, AuditEventBuilder, AuditLogger, BufferedAuditWriter, DatabaseAuditRepository, EventSeverity,
+        EventType, EventOutcome, RedisAuditSpill,
+    },
     error::ErrorMetrics,
+    metrics::AuditMetrics,
     jobs::{JobQueue, RedisJobQueue},
-    session::{SessionManager, SessionConfig, SessionData, SessionState},
+    session::{CreatedSession, SessionConfig, SessionData, SessionLimit, SessionLimitStrategy, SessionManager, SessionState},
 };
 use redis::{aio::ConnectionManager, AsyncCommands};
 use serde_json;
@@ -36,6 +51,9 @@ use tracing::{info, warn};
 use uuid::Uuid;
 use validator::Validate;
 
+/// Queue the auth service enqueues its jobs on, e.g. the emails it sends
+pub const AUTH_JOB_QUEUE: &str = "auth_jobs";
+
 /// Core authentication service providing comprehensive user and tenant management.
 /// 
 /// `AuthService` is the main orchestrator for all authentication-related operations
@@ -123,8 +141,26 @@ pub struct AuthService {
     /// Email verification workflow handler for account activation
     email_verification_workflow: Arc<EmailVerificationWorkflow>,
     
+    /// Password change workflow for logged-in users
+    password_change_workflow: Arc<PasswordChangeWorkflow>,
+    
+    /// Invitation workflow for tenant users, accepted by setting a password
+    invitation_workflow: Arc<InvitationWorkflow>,
+    
+    /// Verification tokens, here for customer portal invitations
+    token_manager: Arc<TokenManager>,
+    
     /// Optional audit logger for security event tracking
     audit_logger: Option<AuditLogger>,
+
+    /// Tenant contexts by tenant id, from the tenants table
+    tenant_resolver: Arc<TenantContextResolver>,
+
+    /// Tenant overrides of the email templates, applied by the workflows
+    email_templates: Arc<EmailTemplateResolver>,
+
+    /// Buffer the audit logger writes through, flushed on shutdown
+    audit_writer: Option<Arc<BufferedAuditWriter>>,
 }
 
 impl AuthService {
@@ -135,12 +171,31 @@ impl AuthService {
     ) -> Result<Self> {
         let repository = AuthRepository::new(db.clone());
         let password_hasher = PasswordHasher::new(&config.security)?;
-        let jwt_service = JwtService::new(&config.jwt)?;
+        let jwt_service = JwtService::new(&config.jwt)?.with_token_refresh(&config.token_refresh);
         let encryption_service = EncryptionService::new(&config.security)?;
         let totp_service = TotpService::new("ERP System".to_string());
 
-        // Initialize audit logger
-        let audit_backend = Arc::new(DatabaseAuditRepository::new(Arc::new(db.main_pool.clone())));
+        // Initialize audit logger; events are buffered and written in batches unless disabled
+        let database_audit: Arc<dyn AuditBackend> = Arc::new(DatabaseAuditRepository::new(Arc::new(db.main_pool.clone())));
+        let audit_writer = if config.audit_writer.enabled {
+            let mut writer = BufferedAuditWriter::new(database_audit.clone(), config.audit_writer.clone())
+                .with_metrics(
+                    AuditMetrics::new(&config.metrics.namespace)
+                        .map_err(|e| Error::new(erp_core::ErrorCode::ConfigurationError, e.to_string()))?,
+                );
+            if !config.audit_writer.spill_key.is_empty() {
+                writer = writer.with_spill(Arc::new(RedisAuditSpill::new(redis.clone(), config.audit_writer.spill_key.clone())));
+            }
+            let writer = Arc::new(writer);
+            writer.spawn();
+            Some(writer)
+        } else {
+            None
+        };
+        let audit_backend: Arc<dyn AuditBackend> = match &audit_writer {
+            Some(writer) => writer.clone(),
+            None => database_audit,
+        };
         let error_metrics = Arc::new(ErrorMetrics::new());
         let audit_logger = Some(AuditLogger::new(
             audit_backend,
@@ -148,7 +203,7 @@ impl AuthService {
         ));
 
         // Initialize job queue
-        let job_queue: Arc<dyn JobQueue> = Arc::new(RedisJobQueue::new(redis.clone(), "auth_jobs"));
+        let job_queue: Arc<dyn JobQueue> = Arc::new(RedisJobQueue::new(redis.clone(), AUTH_JOB_QUEUE));
 
         // Initialize token manager
         let token_manager = Arc::new(TokenManager::new(
@@ -160,13 +215,37 @@ impl AuthService {
         // Initialize email service based on config
         let _email_service = EmailService::new(config.email.clone())?;
 
-        // Initialize workflows
+        // Initialize workflows, sending emails in the tenant's wording where it has one
+        let email_templates = Arc::new(EmailTemplateResolver::new(Arc::new(PostgresEmailTemplateStore::new(
+            db.main_pool.clone(),
+        ))));
+        let default_password_policy = PasswordPolicy::from(&config.password_policy);
+
         let password_reset_config = PasswordResetConfig {
+            min_password_length: u8::try_from(default_password_policy.min_length).unwrap_or(u8::MAX),
+            require_password_complexity: default_password_policy.require_complexity,
+            password_history_count: default_password_policy.history_count,
             company_name: config.app.company_name.clone(),
             base_url: config.app.base_url.clone(),
             ..Default::default()
         };
 
+        let invitation_config = InvitationWorkflowConfig {
+            token_expiry_hours: config.invitations.expiry_hours,
+            send_reminder: config.invitations.send_reminder,
+            base_url: config.app.base_url.clone(),
+            default_policy: default_password_policy.clone(),
+        };
+
+        let password_change_config = PasswordChangeConfig {
+            max_attempts_per_hour: config.password_policy.max_change_attempts_per_hour,
+            default_policy: default_password_policy,
+            token_cutoff_ttl_seconds: config.jwt.refresh_token_expiry as u64,
+            company_name: config.app.company_name.clone(),
+            support_url: config.app.support_url.clone()
+                .unwrap_or_else(|| format!("{}/support", config.app.base_url.trim_end_matches('/'))),
+        };
+
         let email_verification_config = EmailVerificationConfig {
             company_name: config.app.company_name.clone(),
             base_url: config.app.base_url.clone(),
@@ -181,7 +260,7 @@ impl AuthService {
             audit_logger.clone(),
             Arc::new(password_hasher.clone()),
             db.clone(),
-        ));
+        ).with_email_templates(email_templates.clone()));
 
         let email_verification_workflow = Arc::new(EmailVerificationWorkflow::new(
             email_verification_config,
@@ -190,7 +269,16 @@ impl AuthService {
             job_queue.clone(),
             audit_logger.clone(),
             db.clone(),
-        ));
+        ).with_email_templates(email_templates.clone()));
+
+        let invitation_workflow = Arc::new(InvitationWorkflow::new(
+            invitation_config,
+            token_manager.clone(),
+            Arc::new(repository.clone()),
+            job_queue.clone(),
+            audit_logger.clone(),
+            Arc::new(password_hasher.clone()),
+        ).with_email_templates(email_templates.clone()));
 
         // Initialize session manager with configuration-based settings
         let session_config = SessionConfig {
@@ -198,10 +286,22 @@ impl AuthService {
             absolute_timeout: Duration::hours(12),
             cleanup_interval: Duration::minutes(5),
             max_sessions_per_user: 10,
+            session_limit_strategy: SessionLimitStrategy::EvictOldest,
             enable_sliding_window: true,
             require_device_consistency: false,
         };
         let session_manager = Arc::new(SessionManager::new(redis.clone(), session_config));
+        let tenant_resolver = Arc::new(TenantContextResolver::new(db.main_pool.clone()).with_redis(redis.clone()));
+
+        let password_change_workflow = Arc::new(PasswordChangeWorkflow::new(
+            password_change_config,
+            Arc::new(repository.clone()),
+            session_manager.clone(),
+            job_queue.clone(),
+            audit_logger.clone(),
+            Arc::new(password_hasher.clone()),
+            redis.clone(),
+        ).with_email_templates(email_templates.clone()));
 
         Ok(Self {
             repository,
@@ -214,7 +314,13 @@ impl AuthService {
             config,
             password_reset_workflow,
             email_verification_workflow,
+            password_change_workflow,
+            invitation_workflow,
+            token_manager,
             audit_logger,
+            tenant_resolver,
+            email_templates,
+            audit_writer,
         })
     }
 
@@ -314,6 +420,7 @@ impl AuthService {
             tenant_id: TenantId(tenant.id),
             schema_name: tenant.schema_name.clone(),
         };
+        self.tenant_resolver.prime(&tenant_context);
 
         let password_hash = self.password_hasher.hash_password(&request.password)?;
 
@@ -419,7 +526,9 @@ impl AuthService {
     /// - Invalid input format or missing fields
     /// - Tenant not found or inactive
     /// - User not found with provided email
-    /// - Account is disabled or temporarily locked
+    /// - Account is deactivated, deleted or temporarily locked; the error
+    ///   reads like a wrong password and carries the reason as
+    ///   `login_denial` metadata ([`AccountState::login_denial`])
     /// - Password verification fails
     /// - Database or Redis operations fail
     /// 
@@ -466,12 +575,12 @@ impl AuthService {
             .await?
             .ok_or_else(|| Error::new(erp_core::ErrorCode::AuthenticationFailed, "Invalid credentials"))?;
 
-        if !user.is_active {
-            return Err(Error::new(erp_core::ErrorCode::AuthenticationFailed, "Account is disabled"));
-        }
-
-        if user.is_locked() {
-            return Err(Error::new(erp_core::ErrorCode::AuthenticationFailed, "Account is temporarily locked"));
+        // Answered like a wrong password so the caller cannot probe which
+        // emails exist; the reason is in the error metadata and the audit log
+        if let Some(denial) = user.state().login_denial() {
+            self.audit_login_denied(&tenant_context, &user, denial, client_ip.as_deref()).await?;
+            return Err(Error::new(erp_core::ErrorCode::AuthenticationFailed, "Invalid credentials")
+                .add_metadata("login_denial", serde_json::Value::String(denial.to_string())));
         }
 
         let password_hash = user.password_hash
@@ -496,17 +605,12 @@ impl AuthService {
         }
 
         // Create session for successful login
-        let session_data = self.session_manager
-            .create_session(
-                &tenant_context,
-                user.id,
-                client_ip.clone(),
-                user_agent.clone(),
-                None, // device_fingerprint - could be implemented later
-            )
-            .await?;
+        let created = self.open_session(&tenant_context, user.id, client_ip.clone(), user_agent.clone()).await?;
+        let session_data = &created.session;
 
-        let token_pair = self.generate_tokens_for_user(&tenant_context, &user).await?;
+        let token_pair = self
+            .generate_tokens_for_user(&tenant_context, &user, Some(&session_data.session_id))
+            .await?;
         
         self.repository.update_user_login(&tenant_context, user.id).await?;
 
@@ -520,6 +624,7 @@ impl AuthService {
         Ok(LoginOrTwoFactorResponse::Success(LoginResponse {
             access_token: token_pair.access_token,
             refresh_token: token_pair.refresh_token,
+            active_sessions: Some(created.active_sessions),
         }))
     }
 
@@ -554,7 +659,8 @@ impl AuthService {
     /// 
     /// # Arguments
     /// 
-    /// * `request` - 2FA verification request containing session token and TOTP code
+    /// * `request` - 2FA verification request containing session token and TOTP code,
+    ///   or a backup code for users without their authenticator app
     /// 
     /// # Returns
     /// 
@@ -576,6 +682,7 @@ impl AuthService {
     /// - User or tenant no longer exists or is inactive
     /// - 2FA is not configured for the user
     /// - TOTP code is invalid, expired, or already used
+    /// - Backup code matches none of the user's unused ones
     /// - Database or decryption operations fail
     /// 
     /// # Time Window Considerations
@@ -593,6 +700,7 @@ impl AuthService {
     /// let verify_request = Verify2FARequest {
     ///     login_session_token: "eyJ0eXAiOiJKV1QiLCJhbGciOiJSUzI1NiJ9...".to_string(),
     ///     code: "123456".to_string(),
+    ///     backup_code: None,
     /// };
     /// 
     /// let login_response = auth_service.verify_2fa(verify_request).await?;
@@ -633,20 +741,76 @@ impl AuthService {
 
         let secret = self.encryption_service.decrypt_string(&encrypted_secret)?;
 
-        if !self.totp_service.verify_code(&secret, &request.code)? {
-            return Err(Error::new(erp_core::ErrorCode::AuthenticationFailed, "Invalid 2FA code"));
+        match request.backup_code.as_deref() {
+            Some(backup_code) => self.consume_backup_code(&tenant_context, user.id, backup_code).await?,
+            None => {
+                if !self.totp_service.verify_code(&secret, &request.code)? {
+                    return Err(Error::new(erp_core::ErrorCode::AuthenticationFailed, "Invalid 2FA code"));
+                }
+            }
         }
 
-        let token_pair = self.generate_tokens_for_user(&tenant_context, &user).await?;
+        let created = self.open_session(&tenant_context, user.id, None, None).await?;
+        let token_pair = self
+            .generate_tokens_for_user(&tenant_context, &user, Some(&created.session.session_id))
+            .await?;
         
         self.repository.update_user_login(&tenant_context, user.id).await?;
 
         Ok(LoginResponse {
             access_token: token_pair.access_token,
             refresh_token: token_pair.refresh_token,
+            active_sessions: Some(created.active_sessions),
         })
     }
 
+    /// Uses up the user's backup code matching `code`.
+    ///
+    /// Fewer than [`backup_codes::LOW_BACKUP_CODES`] left afterwards, a
+    /// warning is audited so the user can be notified to generate new ones.
+    async fn consume_backup_code(&self, tenant: &TenantContext, user_id: Uuid, code: &str) -> Result<()> {
+        let code = backup_codes::normalize(code);
+        let unused = self.repository.get_unused_backup_codes(tenant, user_id).await?;
+
+        let mut matched = None;
+        for (id, code_hash) in &unused {
+            if self.password_hasher.verify_password(&code, code_hash)? {
+                matched = Some(*id);
+                break;
+            }
+        }
+        // A code used by a concurrent sign-in between the lookup and now is refused too
+        let consumed = match matched {
+            Some(id) => self.repository.consume_backup_code(tenant, user_id, id).await?,
+            None => false,
+        };
+        if !consumed {
+            return Err(Error::new(erp_core::ErrorCode::AuthenticationFailed, "Invalid backup code"));
+        }
+
+        let remaining = unused.len() - 1;
+        info!(
+            tenant_id = %tenant.tenant_id.0,
+            user_id = %user_id,
+            remaining_backup_codes = remaining,
+            "Signed in with a 2FA backup code"
+        );
+        if remaining < backup_codes::LOW_BACKUP_CODES {
+            warn!(
+                tenant_id = %tenant.tenant_id.0,
+                user_id = %user_id,
+                remaining_backup_codes = remaining,
+                "Few 2FA backup codes left"
+            );
+            if let Some(audit_logger) = &self.audit_logger {
+                audit_logger
+                    .log_event(backup_codes::low_backup_codes_event(tenant, user_id, remaining))
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
     /// Refreshes an access token using a valid refresh token.
     /// 
     /// This method allows clients to obtain a new access token without re-authentication
@@ -725,8 +889,9 @@ impl AuthService {
     pub async fn refresh_token(&self, refresh_token: &str) -> Result<erp_core::security::jwt::TokenPair> {
         let claims = self.jwt_service.verify_refresh_token(refresh_token)?;
         
-        let is_revoked = self.is_token_revoked(&claims.jti).await?;
-        if is_revoked {
+        let is_revoked = self.is_token_revoked(&claims.jti).await?
+            || self.is_session_revoked(claims.sid.as_deref()).await?;
+        if is_revoked || self.is_token_superseded(&claims.tenant_id, &claims.sub, claims.iat).await? {
             return Err(Error::new(erp_core::ErrorCode::TokenInvalid, "Token has been revoked"));
         }
 
@@ -751,13 +916,15 @@ impl AuthService {
             .await?
             .ok_or_else(|| Error::new(erp_core::ErrorCode::ResourceNotFound, "User not found"))?;
 
-        if !user.is_active {
+        if matches!(user.state(), AccountState::Deactivated | AccountState::SoftDeleted) {
             return Err(Error::new(erp_core::ErrorCode::AuthenticationFailed, "Account is disabled"));
         }
 
         self.revoke_token(&claims.jti).await?;
 
-        let token_pair = self.generate_tokens_for_user(&tenant_context, &user).await?;
+        let token_pair = self
+            .generate_tokens_for_user(&tenant_context, &user, claims.sid.as_deref())
+            .await?;
 
         Ok(token_pair)
     }
@@ -832,10 +999,12 @@ impl AuthService {
         Ok(())
     }
 
+    /// Tokens for `user`, bound to the login session `session_id` if given
     async fn generate_tokens_for_user(
         &self,
         tenant: &TenantContext,
         user: &User,
+        session_id: Option<&str>,
     ) -> Result<erp_core::security::jwt::TokenPair> {
         let roles = self.repository.get_user_roles(tenant, user.id).await?;
         let permissions = self.repository.get_user_permissions(tenant, user.id).await?;
@@ -846,12 +1015,26 @@ impl AuthService {
             .map(|p| format!("{}:{}", p.resource, p.action))
             .collect();
 
-        self.jwt_service.generate_token_pair(
+        // Portal accounts get the stricter portal token, and none once revoked
+        if let Some(account) = self.repository.get_portal_account(tenant, user.id).await? {
+            if !account.is_active() {
+                return Err(Error::new(erp_core::ErrorCode::AuthenticationFailed, "Portal access is not active"));
+            }
+            return self.jwt_service.generate_portal_token_pair(
+                &user.id.to_string(),
+                &tenant.tenant_id.0.to_string(),
+                &permission_strings,
+                account.scope(),
+                session_id,
+            );
+        }
+
+        self.jwt_service.generate_session_token_pair(
             &user.id.to_string(),
             &tenant.tenant_id.0.to_string(),
             role_names,
             permission_strings,
-            None,
+            session_id,
         )
     }
 
@@ -870,6 +1053,20 @@ impl AuthService {
             let lock_until = Utc::now() + Duration::minutes(15);
             self.repository.lock_user(tenant, user_id, lock_until).await?;
             warn!("User {} locked until {} due to failed login attempts", user_id, lock_until);
+
+            if let Some(audit_logger) = &self.audit_logger {
+                audit_logger.log_event(
+                    AuditEventBuilder::new(EventType::AccountLocked, "User account locked after failed logins")
+                        .severity(EventSeverity::Warning)
+                        .outcome(EventOutcome::Success)
+                        .tenant_id(tenant.tenant_id.0.to_string())
+                        .resource("user", user_id.to_string())
+                        .metadata("previous_state".to_string(), serde_json::Value::String(AccountState::Active.to_string()))
+                        .metadata("state".to_string(), serde_json::Value::String(AccountState::Locked.to_string()))
+                        .metadata("locked_until".to_string(), serde_json::Value::String(lock_until.to_rfc3339()))
+                        .build()
+                ).await?;
+            }
         }
 
         Ok(())
@@ -882,11 +1079,47 @@ impl AuthService {
         Ok(exists)
     }
 
+    async fn is_session_revoked(&self, session_id: Option<&str>) -> Result<bool> {
+        let Some(session_id) = session_id else {
+            return Ok(false);
+        };
+        let mut redis = self.redis.clone();
+        Ok(redis.exists(revoked_session_key(session_id)).await?)
+    }
+
+    /// Ends the session and refuses the tokens bound to it
+    async fn end_session(&self, tenant: &TenantContext, session_id: &str, reason: SessionState) -> Result<()> {
+        self.session_manager.invalidate_session(tenant, session_id, reason).await?;
+        self.revoke_session_tokens(session_id).await
+    }
+
+    /// Refuses the tokens bound to the session for as long as any of them
+    /// could still be valid
+    async fn revoke_session_tokens(&self, session_id: &str) -> Result<()> {
+        let expiry = self.config.jwt.refresh_token_expiry as u64;
+        let mut redis = self.redis.clone();
+        redis
+            .set_ex::<_, _, ()>(revoked_session_key(session_id), Utc::now().timestamp() + expiry as i64, expiry)
+            .await?;
+        Ok(())
+    }
+
+    /// Whether the token was issued before the user's last password change or
+    /// the tenant's last sandbox reset
+    async fn is_token_superseded(&self, tenant_id: &str, user_id: &str, issued_at: i64) -> Result<bool> {
+        let mut redis = self.redis.clone();
+        let cutoffs: Vec<Option<i64>> = redis.mget(&token_cutoff_keys(tenant_id, user_id)).await?;
+        Ok(is_token_superseded(issued_at, cutoffs.into_iter().flatten().max()))
+    }
+
     async fn revoke_token(&self, jti: &str) -> Result<()> {
         let key = format!("revoked_token:{}", jti);
         let expiry = self.config.jwt.refresh_token_expiry as u64;
+        // The value records when the revocation stops mattering, so it can be
+        // cleaned up even if the key loses its TTL
+        let revoked_until = Utc::now().timestamp() + expiry as i64;
         let mut redis = self.redis.clone();
-        redis.set_ex::<_, _, ()>(&key, "1", expiry).await?;
+        redis.set_ex::<_, _, ()>(&key, revoked_until, expiry).await?;
         Ok(())
     }
 
@@ -1058,7 +1291,7 @@ impl AuthService {
         let confirmation = PasswordResetConfirmation {
             token: request.token,
             new_password: request.new_password.clone(),
-            confirm_password: request.new_password, // Assuming validation is done in DTO
+            confirm_password: request.confirm_password,
             client_ip,
         };
 
@@ -1091,6 +1324,63 @@ impl AuthService {
         Ok(is_valid)
     }
 
+    /// Changes the password of a logged-in user.
+    ///
+    /// Verifies the current password, enforces the tenant password policy and
+    /// history, then revokes every session and token the user holds. The caller
+    /// receives a new session and token pair so the device that made the change
+    /// stays signed in while all other devices are signed out.
+    ///
+    /// # Errors
+    ///
+    /// This method returns an error if:
+    /// - The current password is wrong (`InvalidCredentials`)
+    /// - The user was invited and has not set a password yet (`BusinessRuleViolation`)
+    /// - The new password breaks the policy or was used recently (`ValidationFailed`)
+    /// - Too many attempts were made within the last hour (`RateLimitExceeded`)
+    pub async fn change_password(
+        &self,
+        tenant_context: &TenantContext,
+        user_id: Uuid,
+        request: ChangePasswordRequest,
+        client_ip: Option<String>,
+        user_agent: Option<String>,
+    ) -> Result<LoginResponse> {
+        request.validate().map_err(|e| Error::validation(e.to_string()))?;
+
+        let workflow_request = PasswordChangeRequest {
+            current_password: request.current_password,
+            new_password: request.new_password,
+            confirm_password: request.confirm_password,
+            client_ip: client_ip.clone(),
+            user_agent: user_agent.clone(),
+        };
+
+        let user = self.password_change_workflow
+            .change_password(tenant_context, user_id, workflow_request)
+            .await?;
+
+        let created = self.open_session(tenant_context, user.id, client_ip, user_agent).await?;
+        let session_data = &created.session;
+
+        let token_pair = self
+            .generate_tokens_for_user(tenant_context, &user, Some(&session_data.session_id))
+            .await?;
+
+        info!(
+            tenant_id = %tenant_context.tenant_id.0,
+            user_id = %user.id,
+            session_id = %session_data.session_id,
+            "Password changed, session rotated"
+        );
+
+        Ok(LoginResponse {
+            access_token: token_pair.access_token,
+            refresh_token: token_pair.refresh_token,
+            active_sessions: Some(created.active_sessions),
+        })
+    }
+
     // User Management Methods
 
     /// Lists users with pagination and role information.
@@ -1103,6 +1393,7 @@ impl AuthService {
     /// * `tenant_context` - The tenant context for isolation
     /// * `page` - Page number (0-based)
     /// * `limit` - Number of users per page (max 100)
+    /// * `state` - Only users in this account state, all users for `None`
     /// 
     /// # Returns
     /// 
@@ -1112,39 +1403,39 @@ impl AuthService {
         tenant_context: &TenantContext,
         page: u32,
         limit: u32,
+        state: Option<AccountState>,
     ) -> Result<Vec<UserResponse>> {
         let limit = std::cmp::min(limit, 100); // Cap at 100 users per page
         let offset = page * limit;
 
         let users = self.repository
-            .list_users(tenant_context, limit as i64, offset as i64)
+            .list_users(tenant_context, state, limit as i64, offset as i64)
             .await?;
 
         let mut user_responses = Vec::new();
         for user in users {
             let roles = self.repository.get_user_roles(tenant_context, user.id).await?;
-            let role_responses: Vec<RoleResponse> = roles.into_iter().map(|role| RoleResponse {
-                id: role.id,
-                name: role.name,
-                description: role.description,
-                is_editable: role.is_editable,
-            }).collect();
-
-            user_responses.push(UserResponse {
-                id: user.id,
-                email: user.email,
-                first_name: user.first_name,
-                last_name: user.last_name,
-                is_active: user.is_active,
-                email_verified: user.email_verified_at.is_some(),
-                two_factor_enabled: user.two_factor_secret_encrypted.is_some(),
-                roles: role_responses,
-            });
+            user_responses.push(user_response(user, roles));
         }
 
         Ok(user_responses)
     }
 
+    /// Counts the users [`Self::list_users`] pages through.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `tenant_context` - The tenant context for isolation
+    /// * `state` - Only users in this account state, all users for `None`
+    pub async fn count_users(
+        &self,
+        tenant_context: &TenantContext,
+        state: Option<AccountState>,
+    ) -> Result<u64> {
+        let count = self.repository.count_users(tenant_context, state).await?;
+        Ok(count.max(0) as u64)
+    }
+
     /// Gets a specific user by ID with role information.
     /// 
     /// # Arguments
@@ -1166,23 +1457,7 @@ impl AuthService {
             .ok_or_else(|| Error::new(erp_core::ErrorCode::ResourceNotFound, "User not found"))?;
 
         let roles = self.repository.get_user_roles(tenant_context, user.id).await?;
-        let role_responses: Vec<RoleResponse> = roles.into_iter().map(|role| RoleResponse {
-            id: role.id,
-            name: role.name,
-            description: role.description,
-            is_editable: role.is_editable,
-        }).collect();
-
-        Ok(UserResponse {
-            id: user.id,
-            email: user.email,
-            first_name: user.first_name,
-            last_name: user.last_name,
-            is_active: user.is_active,
-            email_verified: user.email_verified_at.is_some(),
-            two_factor_enabled: user.two_factor_secret_encrypted.is_some(),
-            roles: role_responses,
-        })
+        Ok(user_response(user, roles))
     }
 
     /// Updates user information.
@@ -1205,10 +1480,16 @@ impl AuthService {
         request.validate().map_err(|e| Error::validation(e.to_string()))?;
 
         // Check if user exists
-        let _user = self.repository
+        let user = self.repository
             .get_user_by_id(tenant_context, user_id)
             .await?
             .ok_or_else(|| Error::new(erp_core::ErrorCode::ResourceNotFound, "User not found"))?;
+        let previous_state = user.state();
+
+        // Switching a deactivated user back on takes a seat
+        if request.is_active == Some(true) && previous_state == AccountState::Deactivated {
+            self.ensure_free_seat(tenant_context).await?;
+        }
 
         // Update user
         self.repository
@@ -1216,7 +1497,15 @@ impl AuthService {
             .await?;
 
         // Return updated user
-        self.get_user(tenant_context, user_id).await
+        let updated = self.get_user(tenant_context, user_id).await?;
+        if updated.state != previous_state {
+            let event_type = match updated.state {
+                AccountState::Deactivated => EventType::UserDeactivated,
+                _ => EventType::Custom("USER_REACTIVATED".to_string()),
+            };
+            self.audit_state_change(&user, event_type, previous_state, updated.state, None).await?;
+        }
+        Ok(updated)
     }
 
     /// Deletes a user from the system.
@@ -1275,6 +1564,8 @@ impl AuthService {
                 .outcome(erp_core::audit::event::EventOutcome::Success)
                 .resource("user", &user_id.to_string())
                 .metadata("user_email".to_string(), serde_json::Value::String(user.email.clone()))
+                .metadata("previous_state".to_string(), serde_json::Value::String(user.state().to_string()))
+                .metadata("state".to_string(), serde_json::Value::String(AccountState::SoftDeleted.to_string()))
                 .build()
             ).await?;
         }
@@ -1283,15 +1574,223 @@ impl AuthService {
         Ok(())
     }
 
+    /// Reactivates a deactivated or soft-deleted user.
+    /// 
+    /// The user takes a seat again, so the tenant's seat limit must leave
+    /// room for it. A user who never accepted their invitation is sent a new
+    /// one; a user who never completed email verification is sent a new
+    /// verification email.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `tenant_context` - The tenant context for isolation
+    /// * `user_id` - The ID of the user to reactivate
+    /// 
+    /// # Returns
+    /// 
+    /// Returns the reactivated `UserResponse`.
+    pub async fn reactivate_user(
+        &self,
+        tenant_context: &TenantContext,
+        user_id: Uuid,
+    ) -> Result<UserResponse> {
+        let user = self.repository
+            .get_user_by_id(tenant_context, user_id)
+            .await?
+            .ok_or_else(|| Error::new(erp_core::ErrorCode::ResourceNotFound, "User not found"))?;
+
+        let previous_state = user.state();
+        if !previous_state.can_reactivate() {
+            return Err(Error::new(
+                erp_core::ErrorCode::ConflictError,
+                format!("User is {}; only deactivated or deleted users can be reactivated", previous_state),
+            ));
+        }
+
+        self.ensure_free_seat(tenant_context).await?;
+        let user = self.repository.reactivate_user(tenant_context, user_id).await?;
+
+        let verification_required = user.email_verified_at.is_none();
+        if user.password_hash.is_none() {
+            let is_portal_user = self.repository.get_portal_account(tenant_context, user_id).await?.is_some();
+            if !is_portal_user {
+                if let Err(e) = self.invitation_workflow
+                    .send_invitation(tenant_context, user_id, None)
+                    .await
+                {
+                    warn!("Failed to send invitation to reactivated user {}: {}", user_id, e);
+                }
+            }
+        } else if verification_required {
+            let request = EmailVerificationRequest {
+                user_id,
+                client_ip: None,
+            };
+            if let Err(e) = self.email_verification_workflow
+                .send_verification_email(tenant_context, request)
+                .await
+            {
+                warn!("Failed to send verification email to reactivated user {}: {}", user_id, e);
+            }
+        }
+
+        self.audit_state_change(
+            &user,
+            EventType::Custom("USER_REACTIVATED".to_string()),
+            previous_state,
+            user.state(),
+            Some(verification_required),
+        ).await?;
+
+        info!("User reactivated: {} ({}), was {}", user.email, user_id, previous_state);
+        self.get_user(tenant_context, user_id).await
+    }
+
+    /// Fails when the tenant's active users already fill its seat limit.
+    async fn ensure_free_seat(&self, tenant_context: &TenantContext) -> Result<()> {
+        let Some(limit) = self.repository.get_seat_limit(tenant_context.tenant_id.0).await? else {
+            return Ok(());
+        };
+
+        let seated = self.repository.count_seated_users(tenant_context).await?;
+        if seated >= i64::from(limit) {
+            return Err(Error::new(
+                erp_core::ErrorCode::ResourceQuotaExceeded,
+                format!("All {} seats of this tenant are taken; deactivate a user first", limit),
+            ));
+        }
+        Ok(())
+    }
+
+    async fn audit_state_change(
+        &self,
+        user: &User,
+        event_type: EventType,
+        previous_state: AccountState,
+        state: AccountState,
+        verification_required: Option<bool>,
+    ) -> Result<()> {
+        if let Some(audit_logger) = &self.audit_logger {
+            let mut event = AuditEventBuilder::new(
+                event_type,
+                format!("User account {} -> {}", previous_state, state)
+            )
+            .severity(EventSeverity::Info)
+            .outcome(EventOutcome::Success)
+            .resource("user", user.id.to_string())
+            .metadata("user_email".to_string(), serde_json::Value::String(user.email.clone()))
+            .metadata("previous_state".to_string(), serde_json::Value::String(previous_state.to_string()))
+            .metadata("state".to_string(), serde_json::Value::String(state.to_string()));
+            if let Some(verification_required) = verification_required {
+                event = event.metadata("verification_required".to_string(), serde_json::Value::Bool(verification_required));
+            }
+            audit_logger.log_event(event.build()).await?;
+        }
+        Ok(())
+    }
+
+    async fn audit_login_denied(
+        &self,
+        tenant: &TenantContext,
+        user: &User,
+        denial: &str,
+        client_ip: Option<&str>,
+    ) -> Result<()> {
+        if let Some(audit_logger) = &self.audit_logger {
+            let mut event = AuditEventBuilder::new(
+                EventType::AuthenticationFailure,
+                "Login refused by account state"
+            )
+            .severity(EventSeverity::Warning)
+            .outcome(EventOutcome::Failure)
+            .tenant_id(tenant.tenant_id.0.to_string())
+            .resource("user", user.id.to_string())
+            .metadata("reason".to_string(), serde_json::Value::String(denial.to_string()))
+            .metadata("state".to_string(), serde_json::Value::String(user.state().to_string()));
+            if let Some(client_ip) = client_ip {
+                event = event.source_ip(client_ip);
+            }
+            audit_logger.log_event(event.build()).await?;
+        }
+        Ok(())
+    }
+
+    /// Opens a session within the tenant's session limit and audits every
+    /// session it signed out to make room.
+    async fn open_session(
+        &self,
+        tenant: &TenantContext,
+        user_id: Uuid,
+        client_ip: Option<String>,
+        user_agent: Option<String>,
+    ) -> Result<CreatedSession> {
+        let limit = self.session_limit(tenant).await?;
+        let created = self.session_manager
+            .create_session_within(tenant, user_id, client_ip, user_agent, None, limit)
+            .await?;
+
+        // The cap removed the evicted sessions; their tokens are refused from now on
+        for evicted in &created.evicted {
+            self.revoke_session_tokens(&evicted.session_id).await?;
+            if let Some(audit_logger) = &self.audit_logger {
+                audit_logger
+                    .log_event(session_limit::eviction_event(tenant, evicted, &created.session))
+                    .await?;
+            }
+        }
+        Ok(created)
+    }
+
+    /// The session limit that applies to the tenant's users.
+    pub async fn session_limit(&self, tenant: &TenantContext) -> Result<SessionLimit> {
+        session_limit::for_tenant(&self.repository, tenant, self.session_manager.default_limit()).await
+    }
+
+    /// Sets the tenant's session limit; it applies from the users' next login.
+    pub async fn set_session_limit(
+        &self,
+        tenant: &TenantContext,
+        limit: SessionLimit,
+        updated_by: Uuid,
+    ) -> Result<SessionLimit> {
+        session_limit::validate(&limit)?;
+        let previous = self.session_limit(tenant).await?;
+        self.repository.set_tenant_session_limit(tenant, &limit, updated_by).await?;
+
+        if let Some(audit_logger) = &self.audit_logger {
+            audit_logger.log_event(
+                AuditEventBuilder::new(EventType::ConfigurationChanged, "Session limit changed")
+                    .outcome(EventOutcome::Success)
+                    .tenant_id(tenant.tenant_id.0.to_string())
+                    .actor_id(updated_by.to_string())
+                    .resource("session_limit", tenant.tenant_id.0.to_string())
+                    .previous_values(serde_json::to_value(previous)?)
+                    .new_values(serde_json::to_value(limit)?)
+                    .build()
+            ).await?;
+        }
+
+        info!(
+            tenant_id = %tenant.tenant_id.0,
+            max_sessions_per_user = limit.max_sessions_per_user,
+            strategy = limit.strategy.as_str(),
+            sliding_sessions = limit.sliding_sessions,
+            "Session limit changed"
+        );
+        Ok(limit)
+    }
+
     /// Invites a new user to join the tenant.
     /// 
-    /// Creates a new user account and sends an invitation email with
-    /// account setup instructions.
+    /// Creates a new user account without a password and sends an invitation
+    /// email. The user sets their password by accepting the invitation before
+    /// it expires; see [`Self::accept_invitation`].
     /// 
     /// # Arguments
     /// 
     /// * `tenant_context` - The tenant context for isolation
     /// * `request` - Invitation request with user details and role assignments
+    /// * `invited_by` - The tenant user sending the invitation
     /// 
     /// # Returns
     /// 
@@ -1300,6 +1799,7 @@ impl AuthService {
         &self,
         tenant_context: &TenantContext,
         request: InviteUserRequest,
+        invited_by: Option<Uuid>,
     ) -> Result<UserResponse> {
         request.validate().map_err(|e| Error::validation(e.to_string()))?;
 
@@ -1315,6 +1815,8 @@ impl AuthService {
             }
         }
 
+        self.ensure_free_seat(tenant_context).await?;
+
         // Create user without password (will be set during invitation acceptance)
         let user = self.repository
             .create_user(
@@ -1334,23 +1836,298 @@ impl AuthService {
         }
 
         // Send invitation email
-        let invitation_request = EmailVerificationRequest {
-            user_id: user.id,
-            client_ip: None,
-        };
-
-        if let Err(e) = self.email_verification_workflow
-            .send_verification_email(tenant_context, invitation_request)
+        if let Err(e) = self.invitation_workflow
+            .send_invitation(tenant_context, user.id, invited_by)
             .await
         {
             warn!("Failed to send invitation email: {}", e);
-            // Don't fail the invitation if email fails
+            // Don't fail the invitation if email fails; it can be resent
         }
 
         // Return user response
         self.get_user(tenant_context, user.id).await
     }
 
+    /// Sends a user who has not accepted their invitation a new one.
+    /// 
+    /// The link of the previous invitation stops working.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `tenant_context` - The tenant context for isolation
+    /// * `user_id` - The invited user
+    /// * `invited_by` - The tenant user sending the invitation
+    pub async fn resend_invitation(
+        &self,
+        tenant_context: &TenantContext,
+        user_id: Uuid,
+        invited_by: Option<Uuid>,
+    ) -> Result<()> {
+        let user = self.repository
+            .get_user_by_id(tenant_context, user_id)
+            .await?
+            .ok_or_else(|| Error::new(erp_core::ErrorCode::ResourceNotFound, "User not found"))?;
+        if user.deleted_at.is_some() {
+            return Err(Error::new(
+                erp_core::ErrorCode::ConflictError,
+                "User is deleted; reactivate the user to invite them again",
+            ));
+        }
+        if self.repository.get_portal_account(tenant_context, user_id).await?.is_some() {
+            return Err(Error::new(
+                erp_core::ErrorCode::ConflictError,
+                "Customer portal users are invited through their customer",
+            ));
+        }
+
+        self.invitation_workflow
+            .send_invitation(tenant_context, user_id, invited_by)
+            .await?;
+        Ok(())
+    }
+
+    /// The invited user, tenant and inviter of an open invitation, for the
+    /// acceptance page.
+    pub async fn invitation(
+        &self,
+        tenant_context: &TenantContext,
+        token: &str,
+    ) -> Result<InvitationDetails> {
+        self.invitation_workflow.invitation(tenant_context, token).await
+    }
+
+    /// Accepts an invitation: sets the password under the tenant's password
+    /// policy, verifies the email and activates the user.
+    /// 
+    /// The invitation works once; an expired or replaced invitation fails
+    /// with `TokenExpired` or `TokenInvalid`.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `tenant_context` - The tenant context for isolation
+    /// * `token` - The invitation token from the email link
+    /// * `request` - The new password and its confirmation
+    /// * `client_ip` - Address the invitation was accepted from
+    /// 
+    /// # Returns
+    /// 
+    /// Returns the now active `UserResponse`.
+    pub async fn accept_invitation(
+        &self,
+        tenant_context: &TenantContext,
+        token: &str,
+        request: AcceptInvitationRequest,
+        client_ip: Option<String>,
+    ) -> Result<UserResponse> {
+        request.validate().map_err(|e| Error::validation(e.to_string()))?;
+
+        let acceptance = InvitationAcceptance {
+            token: token.to_string(),
+            password: request.password,
+            confirm_password: request.confirm_password,
+            client_ip,
+        };
+        let user = self.invitation_workflow.accept(tenant_context, acceptance).await?;
+
+        self.get_user(tenant_context, user.id).await
+    }
+
+    /// Sends the reminders due for open invitations and deletes the invited
+    /// users whose invitation expired unaccepted, freeing their seats.
+    pub async fn sweep_invitations(&self, tenant_context: &TenantContext) -> Result<InvitationSweep> {
+        self.invitation_workflow.sweep(tenant_context).await
+    }
+
+    // Customer Portal Methods
+
+    /// Invites a customer contact to a portal account.
+    /// 
+    /// Creates a user without a password, bound to the customer and holding
+    /// the `customer_portal` role template, and an invitation token valid for
+    /// seven days. The caller delivers the token to the contact.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `tenant_context` - The tenant context for isolation
+    /// * `request` - The contact's email and name, the customer and whether its hierarchy children are included
+    /// * `invited_by` - The tenant user sending the invitation
+    /// 
+    /// # Returns
+    /// 
+    /// Returns the invited `PortalAccount` and the invitation token.
+    pub async fn invite_portal_user(
+        &self,
+        tenant_context: &TenantContext,
+        request: InvitePortalUserRequest,
+        invited_by: Option<Uuid>,
+    ) -> Result<(PortalAccount, String)> {
+        request.validate().map_err(|e| Error::validation(e.to_string()))?;
+
+        if let Ok(Some(_)) = self.repository.get_user_by_email(tenant_context, &request.email).await {
+            return Err(Error::validation("Email already exists"));
+        }
+
+        let role = self.portal_role(tenant_context).await?;
+        let user = self.repository
+            .create_user(
+                tenant_context,
+                &request.email,
+                None, // Set when the invitation is accepted
+                request.first_name.as_deref().unwrap_or(""),
+                request.last_name.as_deref().unwrap_or(""),
+            )
+            .await?;
+        self.repository.assign_role_to_user(tenant_context, user.id, role.id).await?;
+        let account = self.repository
+            .create_portal_account(tenant_context, user.id, &request, invited_by)
+            .await?;
+
+        let metadata = std::collections::HashMap::from([(
+            "customer_id".to_string(),
+            serde_json::Value::String(request.customer_id.to_string()),
+        )]);
+        let token = self.token_manager
+            .create_token(
+                tenant_context,
+                TokenPurpose::InviteUser,
+                user.id,
+                Some(request.email.clone()),
+                None,
+                None,
+                Some(metadata),
+            )
+            .await?;
+
+        info!(
+            tenant_id = %tenant_context.tenant_id.0,
+            user_id = %user.id,
+            customer_id = %request.customer_id,
+            "Customer portal user invited"
+        );
+
+        Ok((account, token.token))
+    }
+
+    /// Accepts a portal invitation: sets the password and verifies the email
+    /// the invitation was sent to.
+    /// 
+    /// # Arguments
+    /// 
+    /// * `tenant_context` - The tenant context for isolation
+    /// * `customer_id` - The customer the invitation was issued for
+    /// * `request` - The invitation token and the new password
+    /// 
+    /// # Returns
+    /// 
+    /// Returns the now active `PortalAccount`.
+    pub async fn accept_portal_invitation(
+        &self,
+        tenant_context: &TenantContext,
+        customer_id: Uuid,
+        request: AcceptPortalInvitationRequest,
+    ) -> Result<PortalAccount> {
+        request.validate().map_err(|e| Error::validation(e.to_string()))?;
+        validate_password(&request.password)
+            .map_err(|e| Error::validation(e.to_string()))?;
+
+        let invalid = || Error::new(erp_core::ErrorCode::TokenInvalid, "Invalid or expired invitation");
+        let token = self.token_manager
+            .get_token(tenant_context, &request.token, TokenPurpose::InviteUser)
+            .await?
+            .filter(|token| token.is_valid())
+            .ok_or_else(invalid)?;
+        match self.repository.get_portal_account(tenant_context, token.user_id).await? {
+            Some(account) if account.customer_id == customer_id && account.status == "invited" => {}
+            _ => return Err(invalid()),
+        }
+
+        // Consume the token before anything changes, so it works only once
+        self.token_manager
+            .validate_token(tenant_context, &request.token, TokenPurpose::InviteUser, None)
+            .await?;
+        let password_hash = self.password_hasher.hash_password(&request.password)?;
+        self.repository.update_password(tenant_context, token.user_id, &password_hash).await?;
+        self.repository.mark_email_verified(tenant_context, token.user_id).await?;
+        let account = self.repository
+            .activate_portal_account(tenant_context, token.user_id)
+            .await?
+            .ok_or_else(invalid)?;
+
+        info!(
+            tenant_id = %tenant_context.tenant_id.0,
+            user_id = %account.user_id,
+            customer_id = %customer_id,
+            "Customer portal invitation accepted"
+        );
+
+        Ok(account)
+    }
+
+    /// Lists the portal accounts of a customer, revoked ones included.
+    pub async fn list_portal_users(
+        &self,
+        tenant_context: &TenantContext,
+        customer_id: Uuid,
+    ) -> Result<Vec<PortalAccount>> {
+        self.repository.list_portal_accounts(tenant_context, customer_id).await
+    }
+
+    /// Revokes a portal account and ends its active sessions.
+    /// 
+    /// Besides dropping the sessions, every access and refresh token issued
+    /// so far is rejected through the user's token cutoff, and the account
+    /// can no longer obtain new tokens.
+    /// 
+    /// # Returns
+    /// 
+    /// Returns the revoked `PortalAccount`.
+    pub async fn revoke_portal_user(
+        &self,
+        tenant_context: &TenantContext,
+        customer_id: Uuid,
+        user_id: Uuid,
+        revoked_by: Option<Uuid>,
+    ) -> Result<PortalAccount> {
+        let account = self.repository
+            .revoke_portal_account(tenant_context, customer_id, user_id, revoked_by)
+            .await?
+            .ok_or_else(|| Error::not_found("Portal user not found"))?;
+
+        // A second later than now: a token issued within this second is revoked too
+        let cutoff_key = tokens_valid_after_key(&tenant_context.tenant_id.0.to_string(), &user_id.to_string());
+        let mut redis = self.redis.clone();
+        redis
+            .set_ex::<_, _, ()>(&cutoff_key, Utc::now().timestamp() + 1, self.config.jwt.refresh_token_expiry as u64)
+            .await?;
+        self.token_manager
+            .invalidate_user_tokens(tenant_context, user_id, TokenPurpose::InviteUser)
+            .await?;
+        self.revoke_user_sessions(tenant_context.tenant_id.0, user_id, "Customer portal access revoked")
+            .await?;
+
+        Ok(account)
+    }
+
+    /// The `customer_portal` role, created with the template permissions the
+    /// tenant has on first use
+    async fn portal_role(&self, tenant_context: &TenantContext) -> Result<crate::models::Role> {
+        if let Some(role) = self.repository.get_role_by_name(tenant_context, PORTAL_ROLE).await? {
+            return Ok(role);
+        }
+
+        let role = self.repository
+            .create_role(tenant_context, PORTAL_ROLE, Some("Customer portal accounts: customer-scoped reads"), false)
+            .await?;
+        for permission in self.repository.list_permissions(tenant_context).await? {
+            if PORTAL_PERMISSIONS.contains(&permission.to_string().as_str()) {
+                self.repository
+                    .assign_permission_to_role(tenant_context, role.id, permission.id)
+                    .await?;
+            }
+        }
+        Ok(role)
+    }
+
     // Role Management Methods
 
     /// Lists all roles in the tenant.
@@ -1635,16 +2412,105 @@ impl AuthService {
     ) -> Result<Vec<PermissionResponse>> {
         let permissions = self.repository.list_permissions(tenant_context).await?;
         
-        let permission_responses: Vec<PermissionResponse> = permissions.into_iter().map(|permission| PermissionResponse {
-            id: permission.id,
-            resource: permission.resource,
-            action: permission.action,
-            description: permission.description,
-        }).collect();
+        let permission_responses: Vec<PermissionResponse> = permissions.into_iter().map(permission_response).collect();
 
         Ok(permission_responses)
     }
 
+    /// Searches, sorts and pages the roles.
+    ///
+    /// # Arguments
+    ///
+    /// * `tenant_context` - The tenant context for isolation
+    /// * `query` - Search term, sort and page
+    ///
+    /// # Returns
+    ///
+    /// Returns the page of roles with the pagination totals.
+    pub async fn search_roles(&self, tenant_context: &TenantContext, query: &RoleQuery) -> Result<RoleListing> {
+        let (roles, total) = self.repository.search_roles(tenant_context, query).await?;
+
+        Ok(RoleListing {
+            roles: roles.into_iter().map(|role| RoleResponse {
+                id: role.id,
+                name: role.name,
+                description: role.description,
+                is_editable: role.is_editable,
+            }).collect(),
+            pagination: query.page.pagination(total),
+        })
+    }
+
+    /// Searches, filters, sorts and pages the permissions, grouped by resource.
+    ///
+    /// # Arguments
+    ///
+    /// * `tenant_context` - The tenant context for isolation
+    /// * `query` - Search term, resource filter, sort and page
+    ///
+    /// # Returns
+    ///
+    /// Returns the page of permissions grouped by resource, in the order of the sort.
+    pub async fn search_permissions(
+        &self,
+        tenant_context: &TenantContext,
+        query: &PermissionQuery,
+    ) -> Result<PermissionListing> {
+        let (permissions, total) = self.repository.search_permissions(tenant_context, query).await?;
+
+        Ok(PermissionListing {
+            groups: role_catalog::group_by_resource(permissions.into_iter().map(permission_response).collect()),
+            pagination: query.page.pagination(total),
+        })
+    }
+
+    /// Expands a role into its permissions.
+    ///
+    /// # Arguments
+    ///
+    /// * `tenant_context` - The tenant context for isolation
+    /// * `role_id` - The ID of the role
+    ///
+    /// # Returns
+    ///
+    /// Returns the role's permissions grouped by resource, each with the other
+    /// roles granting it. Usage is left for the caller to fill in.
+    pub async fn effective_permissions(
+        &self,
+        tenant_context: &TenantContext,
+        role_id: Uuid,
+    ) -> Result<(RoleResponse, Vec<EffectivePermission>)> {
+        let role = self.get_role(tenant_context, role_id).await?;
+        let permissions = self.repository.get_role_permissions(tenant_context, role_id).await?;
+        let grants = self.repository.get_role_grants(tenant_context, None).await?;
+
+        Ok((role, role_catalog::effective_permissions(role_id, permissions, &grants)))
+    }
+
+    /// Looks up the roles granting a permission.
+    ///
+    /// # Arguments
+    ///
+    /// * `tenant_context` - The tenant context for isolation
+    /// * `permission_id` - The ID of the permission
+    ///
+    /// # Returns
+    ///
+    /// Returns the permission and the roles granting it, by name.
+    pub async fn permission_roles(
+        &self,
+        tenant_context: &TenantContext,
+        permission_id: Uuid,
+    ) -> Result<(PermissionResponse, Vec<RoleRef>)> {
+        let permission = self.repository
+            .get_permission_by_id(tenant_context, permission_id)
+            .await?
+            .ok_or_else(|| Error::new(erp_core::ErrorCode::ResourceNotFound, "Permission not found"))?;
+        let grants = self.repository.get_role_grants(tenant_context, Some(permission_id)).await?;
+
+        Ok((permission_response(permission), role_catalog::roles_granting(permission_id, &grants)))
+    }
+
     /// Impersonates another user (admin functionality).
     /// 
     /// Allows administrators to act on behalf of another user for support purposes.
@@ -1696,7 +2562,7 @@ impl AuthService {
         }
 
         // Generate tokens for target user
-        let token_pair = self.generate_tokens_for_user(tenant_context, &target_user).await?;
+        let token_pair = self.generate_tokens_for_user(tenant_context, &target_user, None).await?;
 
         // Audit log (critical security event)
         if let Some(audit_logger) = &self.audit_logger {
@@ -1724,6 +2590,7 @@ impl AuthService {
         Ok(LoginResponse {
             access_token: token_pair.access_token,
             refresh_token: token_pair.refresh_token,
+            active_sessions: None,
         })
     }
 
@@ -1852,8 +2719,15 @@ impl AuthService {
         // Generate QR code (method takes secret and email only)
         let qr_code = self.totp_service.generate_qr_code(&secret, &user.email)?;
         
-        // Generate backup codes
-        let backup_codes = self.totp_service.generate_backup_codes(8)?;
+        // Generate backup codes; only their hashes are kept
+        let backup_codes = self.totp_service.generate_backup_codes(backup_codes::BACKUP_CODE_COUNT)?;
+        let code_hashes = backup_codes
+            .iter()
+            .map(|code| self.password_hasher.hash_password(code))
+            .collect::<Result<Vec<_>>>()?;
+        self.repository
+            .replace_backup_codes(tenant_context, user_id, &code_hashes)
+            .await?;
 
         Ok(crate::dto::Enable2FAResponse {
             secret,
@@ -1967,6 +2841,25 @@ impl AuthService {
         self.redis.clone()
     }
 
+    pub fn tenant_resolver(&self) -> Arc<TenantContextResolver> {
+        self.tenant_resolver.clone()
+    }
+
+    /// Tenant overrides of the email templates and the chain that picks them
+    pub fn email_templates(&self) -> Arc<EmailTemplateResolver> {
+        self.email_templates.clone()
+    }
+
+    /// The audit buffer, unless audit events are written inline
+    pub fn audit_writer(&self) -> Option<Arc<BufferedAuditWriter>> {
+        self.audit_writer.clone()
+    }
+
+    /// The audit logger shared by the auth workflows
+    pub fn audit_logger(&self) -> Option<AuditLogger> {
+        self.audit_logger.clone()
+    }
+
     // Session Management Methods
 
     /// Logout a user and invalidate their session
@@ -1976,10 +2869,7 @@ impl AuthService {
         session_id: &str,
         user_id: Option<Uuid>,
     ) -> Result<()> {
-        let tenant_context = TenantContext {
-            tenant_id: TenantId(tenant_id),
-            schema_name: format!("tenant_{}", tenant_id),
-        };
+        let tenant_context = self.tenant_resolver.resolve(tenant_id).await?;
 
         // Invalidate the specific session
         self.session_manager
@@ -2002,10 +2892,7 @@ impl AuthService {
         tenant_id: Uuid,
         user_id: Uuid,
     ) -> Result<u32> {
-        let tenant_context = TenantContext {
-            tenant_id: TenantId(tenant_id),
-            schema_name: format!("tenant_{}", tenant_id),
-        };
+        let tenant_context = self.tenant_resolver.resolve(tenant_id).await?;
 
         // Invalidate all user sessions
         let invalidated_count = self.session_manager
@@ -2029,14 +2916,9 @@ impl AuthService {
         user_id: Uuid,
         reason: &str,
     ) -> Result<u32> {
-        let tenant_context = TenantContext {
-            tenant_id: TenantId(tenant_id),
-            schema_name: format!("tenant_{}", tenant_id),
-        };
-
-        // Revoke all user sessions
-        let revoked_count = self.session_manager
-            .invalidate_user_sessions(&tenant_context, user_id, SessionState::Revoked)
+        // Revoke all user sessions along with the tokens bound to them
+        let revoked_count = self
+            .revoke_other_sessions(tenant_id, user_id, None, SessionState::Revoked)
             .await?;
 
         // Log security action
@@ -2066,28 +2948,123 @@ impl AuthService {
         Ok(revoked_count)
     }
 
+    /// Revoke every session of a tenant and reject all tokens issued for it
+    /// so far, as after a sandbox tenant's data was reset
+    pub async fn revoke_tenant_sessions(&self, tenant_id: Uuid, reason: &str) -> Result<u32> {
+        let tenant_context = self.tenant_resolver.resolve(tenant_id).await?;
+
+        // A second later than now: a token issued within this second is revoked too
+        let mut redis = self.redis.clone();
+        redis
+            .set_ex::<_, _, ()>(
+                tenant_tokens_valid_after_key(&tenant_id.to_string()),
+                Utc::now().timestamp() + 1,
+                self.config.jwt.refresh_token_expiry as u64,
+            )
+            .await?;
+        let revoked_count = self.session_manager
+            .invalidate_tenant_sessions(&tenant_context, SessionState::Revoked)
+            .await?;
+
+        if let Some(audit_logger) = &self.audit_logger {
+            audit_logger.log_event(
+                AuditEventBuilder::new(
+                    EventType::Custom("TENANT_SESSIONS_REVOKED".to_string()),
+                    format!("Tenant-wide session revocation: {}", reason)
+                )
+                .severity(EventSeverity::Warning)
+                .outcome(EventOutcome::Success)
+                .resource("tenant", tenant_id.to_string())
+                .metadata("revoked_sessions".to_string(), serde_json::Value::Number(revoked_count.into()))
+                .metadata("reason".to_string(), serde_json::Value::String(reason.to_string()))
+                .build()
+            ).await?;
+        }
+
+        warn!(
+            tenant_id = %tenant_id,
+            revoked_sessions = revoked_count,
+            reason = %reason,
+            "All tenant sessions revoked"
+        );
+
+        Ok(revoked_count)
+    }
+
     /// Get active sessions for a user
     pub async fn get_user_sessions(
         &self,
         tenant_id: Uuid,
         user_id: Uuid,
     ) -> Result<Vec<SessionData>> {
-        let tenant_context = TenantContext {
-            tenant_id: TenantId(tenant_id),
-            schema_name: format!("tenant_{}", tenant_id),
-        };
+        let tenant_context = self.tenant_resolver.resolve(tenant_id).await?;
 
         self.session_manager
             .get_user_sessions(&tenant_context, user_id)
             .await
     }
 
+    /// Signs out one of the user's sessions. A session of another user is
+    /// reported as not found, so session ids cannot be probed across users.
+    pub async fn revoke_session(
+        &self,
+        tenant_id: Uuid,
+        user_id: Uuid,
+        session_id: &str,
+        reason: SessionState,
+    ) -> Result<()> {
+        let tenant_context = self.tenant_resolver.resolve(tenant_id).await?;
+
+        let session = self.session_manager.get_session(&tenant_context, session_id).await?;
+        if session.is_none_or(|session| session.user_id != user_id) {
+            return Err(Error::new(erp_core::ErrorCode::ResourceNotFound, "Session not found"));
+        }
+        self.end_session(&tenant_context, session_id, reason.clone()).await?;
+
+        info!(
+            tenant_id = %tenant_id,
+            user_id = %user_id,
+            session_id = %session_id,
+            reason = ?reason,
+            "User session revoked"
+        );
+
+        Ok(())
+    }
+
+    /// Signs out every session of the user but `keep`, typically the caller's own
+    pub async fn revoke_other_sessions(
+        &self,
+        tenant_id: Uuid,
+        user_id: Uuid,
+        keep: Option<&str>,
+        reason: SessionState,
+    ) -> Result<u32> {
+        let tenant_context = self.tenant_resolver.resolve(tenant_id).await?;
+
+        let mut revoked_count = 0;
+        for session in self.session_manager.get_user_sessions(&tenant_context, user_id).await? {
+            if keep == Some(session.session_id.as_str()) {
+                continue;
+            }
+            self.end_session(&tenant_context, &session.session_id, reason.clone()).await?;
+            revoked_count += 1;
+        }
+
+        info!(
+            tenant_id = %tenant_id,
+            user_id = %user_id,
+            revoked_sessions = revoked_count,
+            kept_session = ?keep,
+            "Other user sessions revoked"
+        );
+
+        Ok(revoked_count)
+    }
+
     /// Get session statistics for a tenant
     pub async fn get_session_stats(&self, tenant_id: Uuid) -> Result<erp_core::session::SessionStats> {
-        let tenant_context = TenantContext {
-            tenant_id: TenantId(tenant_id),
-            schema_name: format!("tenant_{}", tenant_id),
-        };
+        let tenant_context = self.tenant_resolver.resolve(tenant_id).await?;
 
         self.session_manager
             .get_session_stats(&tenant_context)
@@ -2096,10 +3073,7 @@ impl AuthService {
 
     /// Manually trigger session cleanup for a tenant
     pub async fn cleanup_expired_sessions(&self, tenant_id: Uuid) -> Result<u32> {
-        let tenant_context = TenantContext {
-            tenant_id: TenantId(tenant_id),
-            schema_name: format!("tenant_{}", tenant_id),
-        };
+        let tenant_context = self.tenant_resolver.resolve(tenant_id).await?;
 
         let cleaned_count = self.session_manager
             .cleanup_expired_sessions(&tenant_context)
@@ -2122,10 +3096,7 @@ impl AuthService {
         tenant_id: Uuid,
         session_id: &str,
     ) -> Result<Option<SessionData>> {
-        let tenant_context = TenantContext {
-            tenant_id: TenantId(tenant_id),
-            schema_name: format!("tenant_{}", tenant_id),
-        };
+        let tenant_context = self.tenant_resolver.resolve(tenant_id).await?;
 
         self.session_manager
             .get_session(&tenant_context, session_id)
@@ -2140,10 +3111,7 @@ impl AuthService {
         activity: &str,
         metadata: Option<serde_json::Value>,
     ) -> Result<()> {
-        let tenant_context = TenantContext {
-            tenant_id: TenantId(tenant_id),
-            schema_name: format!("tenant_{}", tenant_id),
-        };
+        let tenant_context = self.tenant_resolver.resolve(tenant_id).await?;
 
         // Update last activity metadata
         if let Some(meta) = metadata {
@@ -2196,4 +3164,34 @@ impl AuthService {
 pub enum LoginOrTwoFactorResponse {
     Success(LoginResponse),
     TwoFactorRequired(TwoFactorRequiredResponse),
-}
\ No newline at end of file
+}
+
+fn permission_response(permission: crate::models::Permission) -> PermissionResponse {
+    PermissionResponse {
+        id: permission.id,
+        resource: permission.resource,
+        action: permission.action,
+        description: permission.description,
+    }
+}
+
+fn user_response(user: User, roles: Vec<crate::models::Role>) -> UserResponse {
+    UserResponse {
+        id: user.id,
+        state: user.state(),
+        email: user.email,
+        first_name: user.first_name,
+        last_name: user.last_name,
+        is_active: user.is_active,
+        locked_until: user.locked_until,
+        deleted_at: user.deleted_at,
+        email_verified: user.email_verified_at.is_some(),
+        two_factor_enabled: user.two_factor_secret_encrypted.is_some(),
+        roles: roles.into_iter().map(|role| RoleResponse {
+            id: role.id,
+            name: role.name,
+            description: role.description,
+            is_editable: role.is_editable,
+        }).collect(),
+    }
+}
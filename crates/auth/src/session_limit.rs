@@ -0,0 +1,120 @@
+//! Cap on the concurrent sessions of one user.
+//!
+//! Every tenant gets the session manager's default cap unless it stores its
+//! own in `public.tenant_session_limits`. At the cap a login either signs out
+//! the user's oldest sessions or is refused (see
+//! [`SessionManager::create_session_within`](erp_core::SessionManager::create_session_within)).
+//! Each session signed out that way is audited, so the user can be told that
+//! another device was signed out.
+
+use crate::repository::AuthRepository;
+use erp_core::{
+    audit::{AuditEvent, AuditEventBuilder, EventOutcome, EventSeverity, EventType},
+    error::{Error, ErrorCode, Result},
+    SessionData, SessionLimit, TenantContext,
+};
+
+/// Highest cap a tenant may set
+pub const MAX_SESSIONS_PER_USER: u32 = 1000;
+
+/// Tag of audit events the signed-out user should be notified about
+pub const NOTIFY_USER_TAG: &str = "notify_user";
+
+/// The tenant's own session limit, or `defaults` when the tenant has none
+pub async fn for_tenant(
+    repository: &AuthRepository,
+    tenant: &TenantContext,
+    defaults: SessionLimit,
+) -> Result<SessionLimit> {
+    Ok(repository.get_tenant_session_limit(tenant).await?.unwrap_or(defaults))
+}
+
+pub fn validate(limit: &SessionLimit) -> Result<()> {
+    if limit.max_sessions_per_user == 0 || limit.max_sessions_per_user > MAX_SESSIONS_PER_USER {
+        return Err(Error::new(ErrorCode::ValidationFailed, "Invalid session limit").with_details(format!(
+            "max_sessions_per_user must be between 1 and {}",
+            MAX_SESSIONS_PER_USER
+        )));
+    }
+    Ok(())
+}
+
+/// Audit event for a session signed out to make room for `replaced_by`
+pub fn eviction_event(tenant: &TenantContext, evicted: &SessionData, replaced_by: &SessionData) -> AuditEvent {
+    let mut event = AuditEventBuilder::new(
+        EventType::SessionTerminated,
+        "Session signed out by a newer login at the session limit",
+    )
+    .severity(EventSeverity::Warning)
+    .outcome(EventOutcome::Success)
+    .tenant_id(tenant.tenant_id.0.to_string())
+    .actor_id(evicted.user_id.to_string())
+    .resource("session", &evicted.session_id)
+    .metadata("reason".to_string(), serde_json::Value::String("session_limit".to_string()))
+    .metadata("replaced_by_session".to_string(), serde_json::Value::String(replaced_by.session_id.clone()))
+    .metadata("session_created_at".to_string(), serde_json::Value::String(evicted.created_at.to_rfc3339()))
+    .tag(NOTIFY_USER_TAG);
+    if let Some(client_ip) = &evicted.client_ip {
+        event = event.source_ip(client_ip);
+    }
+    if let Some(user_agent) = &evicted.user_agent {
+        event = event.user_agent(user_agent);
+    }
+    event.build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, Utc};
+    use erp_core::{SessionLimitStrategy, SessionState, TenantId};
+    use std::collections::HashMap;
+    use uuid::Uuid;
+
+    fn session(user_id: Uuid, client_ip: Option<&str>) -> SessionData {
+        let now = Utc::now();
+        SessionData {
+            session_id: Uuid::new_v4().to_string(),
+            user_id,
+            tenant_id: Uuid::new_v4(),
+            created_at: now,
+            last_activity: now,
+            expires_at: now + Duration::hours(12),
+            client_ip: client_ip.map(str::to_string),
+            user_agent: Some("Firefox".to_string()),
+            metadata: HashMap::new(),
+            state: SessionState::Active,
+            token_version: 1,
+            device_fingerprint: None,
+        }
+    }
+
+    #[test]
+    fn test_session_limit_bounds() {
+        let limit = |max_sessions_per_user| SessionLimit { max_sessions_per_user, strategy: SessionLimitStrategy::Reject, sliding_sessions: false };
+
+        assert!(validate(&limit(1)).is_ok());
+        assert!(validate(&limit(MAX_SESSIONS_PER_USER)).is_ok());
+        assert_eq!(validate(&limit(0)).unwrap_err().code, ErrorCode::ValidationFailed);
+        assert!(validate(&limit(MAX_SESSIONS_PER_USER + 1)).is_err());
+    }
+
+    #[test]
+    fn test_eviction_is_audited_for_the_signed_out_device() {
+        let tenant = TenantContext { tenant_id: TenantId(Uuid::new_v4()), schema_name: "tenant_a".to_string() };
+        let user_id = Uuid::new_v4();
+        let evicted = session(user_id, Some("10.0.0.1"));
+        let replaced_by = session(user_id, Some("10.0.0.2"));
+
+        let event = eviction_event(&tenant, &evicted, &replaced_by);
+
+        assert_eq!(event.event_type, EventType::SessionTerminated);
+        assert_eq!(event.actor_id, Some(user_id.to_string()));
+        assert_eq!(event.tenant_id, Some(tenant.tenant_id.0.to_string()));
+        assert_eq!(event.resource_id.as_deref(), Some(evicted.session_id.as_str()));
+        // The device that was signed out, not the one signing in
+        assert_eq!(event.source_ip.as_deref(), Some("10.0.0.1"));
+        assert_eq!(event.metadata["replaced_by_session"], serde_json::json!(replaced_by.session_id));
+        assert!(event.tags.iter().any(|tag| tag == NOTIFY_USER_TAG));
+    }
+}
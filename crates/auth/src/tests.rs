@@ -59,6 +59,7 @@ mod tests {
         let valid_request = Verify2FARequest {
             login_session_token: "valid.jwt.token".to_string(),
             code: "123456".to_string(),
+            backup_code: None,
         };
         assert!(valid_request.validate().is_ok());
 
@@ -66,8 +67,18 @@ mod tests {
         let invalid_code = Verify2FARequest {
             login_session_token: "valid.jwt.token".to_string(),
             code: "12345".to_string(), // Too short
+            backup_code: None,
         };
         assert!(invalid_code.validate().is_err());
+
+        // A backup code replaces the authenticator code
+        let backup = Verify2FARequest {
+            login_session_token: "valid.jwt.token".to_string(),
+            code: String::new(),
+            backup_code: Some("123456".to_string()),
+        };
+        assert!(backup.validate().is_ok());
+        assert!(Verify2FARequest { backup_code: Some(" ".to_string()), ..backup }.validate().is_err());
     }
 
     #[test]
@@ -131,6 +142,7 @@ mod tests {
             last_login_at: None,
             created_at: Utc::now(),
             updated_at: Utc::now(),
+            deleted_at: None,
         };
 
         // Test full_name method
@@ -227,10 +227,10 @@ impl TokenManager {
         token: &str,
         purpose: TokenPurpose,
     ) -> Result<Option<TokenData>> {
-        // Try cache first
+        // Try cache first; used tokens are only in the database
         match self.get_cached_token(tenant, token, purpose).await {
-            Ok(cached) => Ok(cached),
-            Err(_) => {
+            Ok(Some(cached)) => Ok(Some(cached)),
+            Ok(None) | Err(_) => {
                 // Fall back to database
                 self.get_token_from_db(tenant, token, purpose).await
             }
@@ -304,6 +304,98 @@ impl TokenManager {
         Ok(deleted_count)
     }
 
+    /// Unused tokens past half their lifetime that no reminder was sent for yet
+    pub async fn tokens_due_for_reminder(
+        &self,
+        tenant: &TenantContext,
+        purpose: TokenPurpose,
+    ) -> Result<Vec<TokenData>> {
+        let pool = self.db.get_tenant_pool(tenant).await?;
+
+        let tokens = sqlx::query(
+            "SELECT token FROM verification_tokens
+             WHERE tenant_id = $1 AND purpose = $2 AND used = false AND expires_at > NOW()
+               AND created_at + (expires_at - created_at) / 2 <= NOW()
+               AND (metadata IS NULL OR NOT metadata ? 'reminded_at')"
+        )
+        .bind(tenant.tenant_id.0)
+        .bind(purpose.to_string())
+        .fetch_all(pool.get())
+        .await?;
+
+        let mut due = Vec::with_capacity(tokens.len());
+        for row in tokens {
+            let token: String = row.try_get("token")?;
+            if let Some(token_data) = self.get_token_from_db(tenant, &token, purpose).await? {
+                due.push(token_data);
+            }
+        }
+        Ok(due)
+    }
+
+    /// Record that a reminder was sent for the token
+    pub async fn mark_reminded(&self, tenant: &TenantContext, token_data: &TokenData) -> Result<()> {
+        let pool = self.db.get_tenant_pool(tenant).await?;
+
+        sqlx::query(
+            "UPDATE verification_tokens
+             SET metadata = COALESCE(metadata, '{}'::jsonb) || jsonb_build_object('reminded_at', NOW())
+             WHERE token = $1 AND tenant_id = $2"
+        )
+        .bind(&token_data.token)
+        .bind(tenant.tenant_id.0)
+        .execute(pool.get())
+        .await?;
+
+        self.remove_token_from_cache(token_data).await
+    }
+
+    /// Delete the tokens of `purpose` that expired unused; the users they were for
+    pub async fn delete_expired_unused_tokens(
+        &self,
+        tenant: &TenantContext,
+        purpose: TokenPurpose,
+    ) -> Result<Vec<Uuid>> {
+        let pool = self.db.get_tenant_pool(tenant).await?;
+
+        let user_ids = sqlx::query_scalar(
+            "DELETE FROM verification_tokens
+             WHERE tenant_id = $1 AND purpose = $2 AND used = false AND expires_at < NOW()
+             RETURNING user_id"
+        )
+        .bind(tenant.tenant_id.0)
+        .bind(purpose.to_string())
+        .fetch_all(pool.get())
+        .await?;
+
+        Ok(user_ids)
+    }
+
+    /// Whether the user has an unused, unexpired token of `purpose`
+    pub async fn has_open_token(
+        &self,
+        tenant: &TenantContext,
+        user_id: Uuid,
+        purpose: TokenPurpose,
+    ) -> Result<bool> {
+        let pool = self.db.get_tenant_pool(tenant).await?;
+
+        let open: bool = sqlx::query_scalar(
+            "SELECT EXISTS (
+                 SELECT 1 FROM verification_tokens
+                 WHERE tenant_id = $1 AND user_id = $2 AND purpose = $3
+                   AND used = false AND expires_at > NOW()
+             )"
+        )
+        .bind(tenant.tenant_id.0)
+        .bind(user_id)
+        .bind(purpose.to_string())
+        .fetch_one(pool.get())
+        .await?;
+
+        Ok(open)
+    }
+
     /// Get token statistics
     pub async fn get_token_stats(&self, tenant: &TenantContext) -> Result<TokenStats> {
         let pool = self.db.get_tenant_pool(tenant).await?;
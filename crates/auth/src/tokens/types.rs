@@ -10,6 +10,8 @@ pub enum TokenPurpose {
     PasswordReset,
     InviteUser,
     ChangeEmail,
+    /// Invitation of a tenant user, accepted by setting a password
+    Invitation,
 }
 
 impl TokenPurpose {
@@ -20,6 +22,7 @@ impl TokenPurpose {
             TokenPurpose::PasswordReset => 1,       // 1 hour
             TokenPurpose::InviteUser => 168,        // 7 days
             TokenPurpose::ChangeEmail => 24,        // 24 hours
+            TokenPurpose::Invitation => 168,        // 7 days
         }
     }
 
@@ -30,6 +33,7 @@ impl TokenPurpose {
             TokenPurpose::PasswordReset => false,    // Only latest reset token should be valid
             TokenPurpose::InviteUser => true,        // Can have multiple invites
             TokenPurpose::ChangeEmail => false,
+            TokenPurpose::Invitation => false,       // A re-invite replaces the previous link
         }
     }
 
@@ -40,6 +44,7 @@ impl TokenPurpose {
             TokenPurpose::PasswordReset => "reset_password",
             TokenPurpose::InviteUser => "invite_user",
             TokenPurpose::ChangeEmail => "change_email",
+            TokenPurpose::Invitation => "invitation",
         }
     }
 }
@@ -51,6 +56,7 @@ impl std::fmt::Display for TokenPurpose {
             TokenPurpose::PasswordReset => write!(f, "password_reset"),
             TokenPurpose::InviteUser => write!(f, "invite_user"),
             TokenPurpose::ChangeEmail => write!(f, "change_email"),
+            TokenPurpose::Invitation => write!(f, "invitation"),
         }
     }
 }
@@ -242,6 +248,7 @@ impl TryFrom<VerificationToken> for TokenData {
             "password_reset" => TokenPurpose::PasswordReset,
             "invite_user" => TokenPurpose::InviteUser,
             "change_email" => TokenPurpose::ChangeEmail,
+            "invitation" => TokenPurpose::Invitation,
             _ => return Err(serde_json::Error::io(std::io::Error::new(std::io::ErrorKind::InvalidData, "Invalid token purpose"))),
         };
 
@@ -295,6 +302,8 @@ mod tests {
         assert!(!TokenPurpose::EmailVerification.allows_multiple_tokens());
         assert!(!TokenPurpose::PasswordReset.allows_multiple_tokens());
         assert!(TokenPurpose::InviteUser.allows_multiple_tokens());
+        assert_eq!(TokenPurpose::Invitation.default_expiry_hours(), 168);
+        assert!(!TokenPurpose::Invitation.allows_multiple_tokens());
     }
 
     #[test]
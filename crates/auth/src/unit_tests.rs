@@ -80,6 +80,7 @@ mod unit_tests {
             last_login_at: None,
             created_at: Utc::now(),
             updated_at: Utc::now(),
+            deleted_at: None,
         };
 
         // Test full_name method
@@ -110,6 +111,53 @@ mod unit_tests {
         assert!(user.has_2fa_enabled());
     }
 
+    #[test]
+    fn test_account_state_precedence() {
+        use chrono::{Duration, Utc};
+
+        let mut user = User {
+            id: Uuid::new_v4(),
+            email: "state@example.com".to_string(),
+            password_hash: None,
+            first_name: None,
+            last_name: None,
+            is_active: true,
+            locked_until: Some(Utc::now() + Duration::minutes(15)),
+            email_verified_at: None,
+            two_factor_secret_encrypted: None,
+            two_factor_enabled_at: None,
+            last_login_at: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            deleted_at: None,
+        };
+        assert_eq!(user.state(), AccountState::Locked);
+        assert_eq!(user.state().login_denial(), Some("ACCOUNT_LOCKED"));
+        assert!(!user.state().can_reactivate());
+
+        // An administrator's deactivation outranks a running lockout
+        user.is_active = false;
+        assert_eq!(user.state(), AccountState::Deactivated);
+        assert_eq!(user.state().login_denial(), Some("ACCOUNT_DEACTIVATED"));
+
+        user.deleted_at = Some(Utc::now());
+        assert_eq!(user.state(), AccountState::SoftDeleted);
+        assert_eq!(user.state().login_denial(), Some("ACCOUNT_DELETED"));
+        assert!(user.state().can_reactivate());
+
+        user.deleted_at = None;
+        user.is_active = true;
+        user.locked_until = Some(Utc::now() - Duration::minutes(1));
+        assert_eq!(user.state(), AccountState::Active);
+        assert_eq!(user.state().login_denial(), None);
+
+        for state in AccountState::ALL {
+            assert_eq!(AccountState::parse(state.as_str()), Some(state));
+            assert_eq!(serde_json::to_string(&state).unwrap(), format!("\"{}\"", state));
+        }
+        assert_eq!(AccountState::parse("deleted"), None);
+    }
+
     #[test]
     fn test_tenant_status_serialization() {
         use serde_json;
@@ -1,4 +1,4 @@
-use crate::email::{EmailJobData, VerificationEmailTemplate, WelcomeEmailTemplate};
+use crate::email::{email_job, EmailTemplateResolver, VerificationEmailTemplate, WelcomeEmailTemplate};
 use crate::models::User;
 use crate::repository::UserRepository;
 use crate::tokens::{TokenManager, TokenPurpose};
@@ -62,6 +62,7 @@ pub struct EmailVerificationWorkflow {
     job_queue: Arc<dyn JobQueue>,
     audit_logger: Option<AuditLogger>,
     db: DatabasePool,
+    email_templates: Option<Arc<EmailTemplateResolver>>,
 }
 
 impl EmailVerificationWorkflow {
@@ -80,9 +81,16 @@ impl EmailVerificationWorkflow {
             job_queue,
             audit_logger,
             db,
+            email_templates: None,
         }
     }
 
+    /// Send emails in the tenant's wording where it overrides the built-in templates
+    pub fn with_email_templates(mut self, email_templates: Arc<EmailTemplateResolver>) -> Self {
+        self.email_templates = Some(email_templates);
+        self
+    }
+
     /// Send email verification to user
     pub async fn send_verification_email(
         &self,
@@ -400,12 +408,13 @@ impl EmailVerificationWorkflow {
         };
 
         // Create email job
-        let email_job = EmailJobData::from_template(
+        let email_job = email_job(
+            self.email_templates.as_deref(),
             &user.email,
             &email_template,
-            Some(tenant.tenant_id.0.to_string()),
+            tenant.tenant_id.0,
             Some(user.id.to_string()),
-        ).with_metadata("workflow".to_string(), serde_json::Value::String("email_verification".to_string()));
+        ).await.with_metadata("workflow".to_string(), serde_json::Value::String("email_verification".to_string()));
 
         // Create a proper queued job from the serializable job
         let queued_job = erp_core::jobs::types::QueuedJob::new(&email_job)?;
@@ -433,12 +442,13 @@ impl EmailVerificationWorkflow {
         };
 
         // Create email job
-        let email_job = EmailJobData::from_template(
+        let email_job = email_job(
+            self.email_templates.as_deref(),
             &user.email,
             &email_template,
-            Some(tenant.tenant_id.0.to_string()),
+            tenant.tenant_id.0,
             Some(user.id.to_string()),
-        ).with_metadata("workflow".to_string(), serde_json::Value::String("welcome".to_string()));
+        ).await.with_metadata("workflow".to_string(), serde_json::Value::String("welcome".to_string()));
 
         // Create a proper queued job from the serializable job
         let queued_job = erp_core::jobs::types::QueuedJob::new(&email_job)?;
@@ -0,0 +1,396 @@
+use crate::email::{email_job, EmailTemplateResolver, InvitationEmailTemplate};
+use crate::models::User;
+use crate::password_policy::PasswordPolicy;
+use crate::repository::UserRepository;
+use crate::tokens::{TokenData, TokenManager, TokenPurpose};
+use chrono::{DateTime, Utc};
+use erp_core::{
+    audit::{AuditEvent, AuditLogger, event::EventOutcome, EventSeverity, EventType},
+    error::{Error, ErrorCode, Result},
+    jobs::JobQueue,
+    security::PasswordHasher,
+    TenantContext,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+/// Configuration for the user invitation workflow
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InvitationWorkflowConfig {
+    /// Invitation expiry in hours (default: 7 days)
+    pub token_expiry_hours: u32,
+    /// Send a reminder when an invitation is still open halfway to its expiry
+    pub send_reminder: bool,
+    /// Base URL for acceptance links
+    pub base_url: String,
+    /// Rules for tenants without their own password policy
+    pub default_policy: PasswordPolicy,
+}
+
+impl Default for InvitationWorkflowConfig {
+    fn default() -> Self {
+        Self {
+            token_expiry_hours: 168,
+            send_reminder: true,
+            base_url: "https://localhost:3000".to_string(),
+            default_policy: PasswordPolicy {
+                min_length: 8,
+                require_complexity: true,
+                history_count: 5,
+            },
+        }
+    }
+}
+
+/// What the acceptance page shows about an open invitation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InvitationDetails {
+    pub email: String,
+    pub first_name: Option<String>,
+    pub last_name: Option<String>,
+    /// Name of the tenant the user is invited to
+    pub tenant_name: String,
+    /// Name of the user who sent the invitation, if known
+    pub inviter_name: Option<String>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Request data for accepting an invitation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InvitationAcceptance {
+    pub token: String,
+    pub password: String,
+    pub confirm_password: String,
+    pub client_ip: Option<String>,
+}
+
+/// What one invitation sweep of a tenant did
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct InvitationSweep {
+    /// Reminders sent for invitations halfway to their expiry
+    pub reminded: u32,
+    /// Invited users deleted because their invitation expired unaccepted
+    pub expired_users: Vec<Uuid>,
+}
+
+/// The invitation token if it can still be accepted, otherwise why not
+fn open_invitation(token: Option<TokenData>) -> Result<TokenData> {
+    match token {
+        None => Err(Error::new(ErrorCode::TokenInvalid, "Invalid invitation")),
+        Some(token) if token.used => Err(Error::new(ErrorCode::TokenInvalid, "Invitation has already been accepted")),
+        Some(token) if token.is_expired() => Err(Error::new(ErrorCode::TokenExpired, "Invitation has expired")),
+        Some(token) => Ok(token),
+    }
+}
+
+fn display_name(user: &User) -> String {
+    let name = format!(
+        "{} {}",
+        user.first_name.as_deref().unwrap_or_default(),
+        user.last_name.as_deref().unwrap_or_default()
+    );
+    match name.trim() {
+        "" => user.email.clone(),
+        name => name.to_string(),
+    }
+}
+
+/// Invitation of tenant users: the invited user accepts by choosing a
+/// password, which also verifies their email
+pub struct InvitationWorkflow {
+    config: InvitationWorkflowConfig,
+    token_manager: Arc<TokenManager>,
+    user_repository: Arc<UserRepository>,
+    job_queue: Arc<dyn JobQueue>,
+    audit_logger: Option<AuditLogger>,
+    password_hasher: Arc<PasswordHasher>,
+    email_templates: Option<Arc<EmailTemplateResolver>>,
+}
+
+impl InvitationWorkflow {
+    pub fn new(
+        config: InvitationWorkflowConfig,
+        token_manager: Arc<TokenManager>,
+        user_repository: Arc<UserRepository>,
+        job_queue: Arc<dyn JobQueue>,
+        audit_logger: Option<AuditLogger>,
+        password_hasher: Arc<PasswordHasher>,
+    ) -> Self {
+        Self {
+            config,
+            token_manager,
+            user_repository,
+            job_queue,
+            audit_logger,
+            password_hasher,
+            email_templates: None,
+        }
+    }
+
+    /// Send emails in the tenant's wording where it overrides the built-in templates
+    pub fn with_email_templates(mut self, email_templates: Arc<EmailTemplateResolver>) -> Self {
+        self.email_templates = Some(email_templates);
+        self
+    }
+
+    /// Send an invitation to a user without a password; any earlier
+    /// invitation of the user stops working
+    pub async fn send_invitation(
+        &self,
+        tenant: &TenantContext,
+        user_id: Uuid,
+        invited_by: Option<Uuid>,
+    ) -> Result<TokenData> {
+        let user = self.user_repository.find_by_id(tenant, user_id).await?
+            .ok_or_else(|| Error::new(ErrorCode::ResourceNotFound, "User not found"))?;
+        if user.password_hash.is_some() {
+            return Err(Error::new(ErrorCode::ConflictError, "User has already accepted an invitation"));
+        }
+
+        let metadata = invited_by.map(|inviter| {
+            HashMap::from([("invited_by".to_string(), serde_json::Value::String(inviter.to_string()))])
+        });
+        // Invitations allow one open token per user, so this replaces the previous one
+        let token_data = self.token_manager.create_token(
+            tenant,
+            TokenPurpose::Invitation,
+            user.id,
+            Some(user.email.clone()),
+            Some(self.config.token_expiry_hours),
+            None,
+            metadata,
+        ).await?;
+
+        self.queue_email(tenant, &user, &token_data, false).await?;
+
+        if let Some(audit_logger) = &self.audit_logger {
+            audit_logger.log_event(
+                AuditEvent::builder(
+                    EventType::Custom("USER_INVITED".to_string()),
+                    "User invitation sent"
+                )
+                .severity(EventSeverity::Info)
+                .outcome(EventOutcome::Success)
+                .resource("user", user.id.to_string())
+                .metadata("email".to_string(), serde_json::Value::String(user.email.clone()))
+                .metadata("expires_at".to_string(), serde_json::Value::String(token_data.expires_at.to_rfc3339()))
+                .build()
+            ).await?;
+        }
+
+        info!(user_id = %user.id, expires_at = %token_data.expires_at, "User invitation sent");
+        Ok(token_data)
+    }
+
+    /// The invited user, tenant and inviter of an open invitation
+    pub async fn invitation(&self, tenant: &TenantContext, token: &str) -> Result<InvitationDetails> {
+        let token_data = open_invitation(
+            self.token_manager.get_token(tenant, token, TokenPurpose::Invitation).await?
+        )?;
+        let user = self.invited_user(tenant, token_data.user_id).await?;
+
+        Ok(InvitationDetails {
+            email: user.email,
+            first_name: user.first_name,
+            last_name: user.last_name,
+            tenant_name: self.tenant_name(tenant).await?,
+            inviter_name: self.inviter_name(tenant, &token_data).await?,
+            expires_at: token_data.expires_at,
+        })
+    }
+
+    /// Set the password of the invited user, verify their email and make
+    /// them active; the invitation works only once
+    pub async fn accept(&self, tenant: &TenantContext, acceptance: InvitationAcceptance) -> Result<User> {
+        let token_data = open_invitation(
+            self.token_manager.get_token(tenant, &acceptance.token, TokenPurpose::Invitation).await?
+        )?;
+        self.invited_user(tenant, token_data.user_id).await?;
+
+        if acceptance.password != acceptance.confirm_password {
+            return Err(Error::validation("Passwords do not match"));
+        }
+        let policy = PasswordPolicy::for_tenant(&self.user_repository, tenant, &self.config.default_policy).await?;
+        policy.validate(&acceptance.password)?;
+
+        // Consume the token before anything changes, so it works only once
+        self.token_manager.validate_token(
+            tenant,
+            &acceptance.token,
+            TokenPurpose::Invitation,
+            acceptance.client_ip.clone(),
+        ).await?;
+        let password_hash = self.password_hasher.hash_password(&acceptance.password)?;
+        let user = self.user_repository
+            .accept_invitation(tenant, token_data.user_id, &password_hash)
+            .await?
+            .ok_or_else(|| Error::new(ErrorCode::TokenInvalid, "Invitation has already been accepted"))?;
+
+        if let Some(audit_logger) = &self.audit_logger {
+            audit_logger.log_event(
+                AuditEvent::builder(
+                    EventType::Custom("INVITATION_ACCEPTED".to_string()),
+                    "User invitation accepted"
+                )
+                .severity(EventSeverity::Info)
+                .outcome(EventOutcome::Success)
+                .resource("user", user.id.to_string())
+                .metadata("email".to_string(), serde_json::Value::String(user.email.clone()))
+                .metadata("client_ip".to_string(),
+                    serde_json::Value::String(acceptance.client_ip.unwrap_or_default()))
+                .build()
+            ).await?;
+        }
+
+        info!(user_id = %user.id, email = %user.email, "User invitation accepted");
+        Ok(user)
+    }
+
+    /// Send reminders for invitations halfway to their expiry and delete the
+    /// invited users whose invitation expired unaccepted
+    pub async fn sweep(&self, tenant: &TenantContext) -> Result<InvitationSweep> {
+        let mut sweep = InvitationSweep::default();
+
+        if self.config.send_reminder {
+            for token_data in self.token_manager.tokens_due_for_reminder(tenant, TokenPurpose::Invitation).await? {
+                let Some(user) = self.user_repository.find_by_id(tenant, token_data.user_id).await? else {
+                    continue;
+                };
+                if user.password_hash.is_some() || user.deleted_at.is_some() {
+                    continue;
+                }
+                match self.queue_email(tenant, &user, &token_data, true).await {
+                    Ok(()) => {
+                        self.token_manager.mark_reminded(tenant, &token_data).await?;
+                        sweep.reminded += 1;
+                    }
+                    Err(e) => warn!(user_id = %user.id, error = %e, "Failed to queue invitation reminder"),
+                }
+            }
+        }
+
+        for user_id in self.token_manager.delete_expired_unused_tokens(tenant, TokenPurpose::Invitation).await? {
+            let Some(user) = self.user_repository.find_by_id(tenant, user_id).await? else {
+                continue;
+            };
+            // Accepted, deleted already, or invited again meanwhile
+            if user.password_hash.is_some() || user.deleted_at.is_some() || sweep.expired_users.contains(&user_id) {
+                continue;
+            }
+            if self.token_manager.has_open_token(tenant, user_id, TokenPurpose::Invitation).await? {
+                continue;
+            }
+            self.user_repository.soft_delete_user(tenant, user_id).await?;
+            sweep.expired_users.push(user_id);
+
+            if let Some(audit_logger) = &self.audit_logger {
+                audit_logger.log_event(
+                    AuditEvent::builder(
+                        EventType::Custom("INVITATION_EXPIRED".to_string()),
+                        "Invited user deleted after the invitation expired"
+                    )
+                    .severity(EventSeverity::Info)
+                    .outcome(EventOutcome::Success)
+                    .resource("user", user.id.to_string())
+                    .metadata("email".to_string(), serde_json::Value::String(user.email.clone()))
+                    .build()
+                ).await?;
+            }
+        }
+
+        if sweep.reminded > 0 || !sweep.expired_users.is_empty() {
+            info!(
+                tenant_id = %tenant.tenant_id.0,
+                reminded = sweep.reminded,
+                expired = sweep.expired_users.len(),
+                "Invitations swept"
+            );
+        }
+        Ok(sweep)
+    }
+
+    /// The user an invitation is for, if they can still accept it
+    async fn invited_user(&self, tenant: &TenantContext, user_id: Uuid) -> Result<User> {
+        match self.user_repository.find_by_id(tenant, user_id).await? {
+            Some(user) if user.password_hash.is_none() && user.deleted_at.is_none() => Ok(user),
+            Some(user) if user.password_hash.is_some() => {
+                Err(Error::new(ErrorCode::TokenInvalid, "Invitation has already been accepted"))
+            }
+            _ => Err(Error::new(ErrorCode::TokenInvalid, "Invalid invitation")),
+        }
+    }
+
+    async fn tenant_name(&self, tenant: &TenantContext) -> Result<String> {
+        Ok(self.user_repository
+            .get_tenant_by_id(tenant.tenant_id.0)
+            .await?
+            .map(|tenant| tenant.name)
+            .unwrap_or_default())
+    }
+
+    /// Name of the user recorded as `invited_by` on the token, if they still exist
+    async fn inviter_name(&self, tenant: &TenantContext, token_data: &TokenData) -> Result<Option<String>> {
+        let inviter_id = token_data.metadata
+            .get("invited_by")
+            .and_then(|value| value.as_str())
+            .and_then(|id| id.parse().ok());
+        match inviter_id {
+            Some(inviter_id) => Ok(self.user_repository
+                .find_by_id(tenant, inviter_id)
+                .await?
+                .map(|inviter| display_name(&inviter))),
+            None => Ok(None),
+        }
+    }
+
+    async fn queue_email(&self, tenant: &TenantContext, user: &User, token_data: &TokenData, reminder: bool) -> Result<()> {
+        let tenant_name = self.tenant_name(tenant).await?;
+        let inviter_name = self.inviter_name(tenant, token_data).await?;
+
+        let email_template = InvitationEmailTemplate {
+            user_name: display_name(user),
+            inviter_name: inviter_name.unwrap_or_else(|| format!("An administrator of {}", tenant_name)),
+            company_name: tenant_name,
+            accept_url: format!("{}/auth/invitations/{}", self.config.base_url.trim_end_matches('/'), token_data.token),
+            expires_at: token_data.expires_at,
+            reminder,
+        };
+
+        let email_job = email_job(
+            self.email_templates.as_deref(),
+            &user.email,
+            &email_template,
+            tenant.tenant_id.0,
+            Some(user.id.to_string()),
+        ).await.with_metadata("workflow".to_string(), serde_json::Value::String("invitation".to_string()));
+
+        let queued_job = erp_core::jobs::types::QueuedJob::new(&email_job)?;
+        self.job_queue.enqueue(queued_job).await?;
+
+        debug!(user_id = %user.id, reminder, "Invitation email queued");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_open_invitation() {
+        let open = TokenData::new(TokenPurpose::Invitation, Uuid::new_v4(), Uuid::new_v4(), None);
+        assert!(open_invitation(Some(open.clone())).is_ok());
+        assert_eq!(open_invitation(None).unwrap_err().code, ErrorCode::TokenInvalid);
+
+        let mut accepted = open.clone();
+        accepted.mark_used(None);
+        assert_eq!(open_invitation(Some(accepted)).unwrap_err().message, "Invitation has already been accepted");
+
+        let mut expired = open;
+        expired.expires_at = Utc::now() - chrono::Duration::seconds(1);
+        assert_eq!(open_invitation(Some(expired)).unwrap_err().code, ErrorCode::TokenExpired);
+    }
+}
@@ -1,5 +1,9 @@
 pub mod password_reset;
+pub mod password_change;
 pub mod email_verification;
+pub mod invitation;
 
 pub use password_reset::{PasswordResetWorkflow, PasswordResetConfig, PasswordResetRequest, PasswordResetConfirmation};
-pub use email_verification::{EmailVerificationWorkflow, EmailVerificationConfig, EmailVerificationRequest, EmailVerificationConfirmation};
\ No newline at end of file
+pub use password_change::{PasswordChangeWorkflow, PasswordChangeConfig, PasswordChangeRequest};
+pub use email_verification::{EmailVerificationWorkflow, EmailVerificationConfig, EmailVerificationRequest, EmailVerificationConfirmation};
+pub use invitation::{InvitationWorkflow, InvitationWorkflowConfig, InvitationDetails, InvitationAcceptance, InvitationSweep};
@@ -0,0 +1,387 @@
+use crate::email::{email_job, EmailTemplateResolver, PasswordChangedEmailTemplate};
+use crate::models::User;
+use crate::password_policy::PasswordPolicy;
+use crate::repository::UserRepository;
+use chrono::Utc;
+use erp_core::{
+    audit::{AuditEvent, AuditLogger, event::EventOutcome, EventSeverity, EventType},
+    error::{Error, ErrorCode, Result},
+    jobs::JobQueue,
+    security::{tokens_valid_after_key, PasswordHasher},
+    session::{SessionManager, SessionState},
+    TenantContext,
+};
+use redis::{aio::ConnectionManager, AsyncCommands};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+/// Configuration for the password change workflow
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PasswordChangeConfig {
+    /// Maximum password change attempts per hour per user
+    pub max_attempts_per_hour: u32,
+    /// Policy used for tenants without their own password policy
+    pub default_policy: PasswordPolicy,
+    /// How long the token cutoff is kept; must cover the refresh token lifetime
+    pub token_cutoff_ttl_seconds: u64,
+    /// Company name for email templates
+    pub company_name: String,
+    /// Support link included in the notification email
+    pub support_url: String,
+}
+
+impl Default for PasswordChangeConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts_per_hour: 5,
+            default_policy: PasswordPolicy {
+                min_length: 8,
+                require_complexity: true,
+                history_count: 5,
+            },
+            token_cutoff_ttl_seconds: 7 * 24 * 3600,
+            company_name: "ERP System".to_string(),
+            support_url: "https://localhost:3000/support".to_string(),
+        }
+    }
+}
+
+/// Request data for a password change by a logged-in user
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PasswordChangeRequest {
+    pub current_password: String,
+    pub new_password: String,
+    pub confirm_password: String,
+    pub client_ip: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+/// Password change workflow service
+///
+/// Verifies the current password, applies the tenant password policy and
+/// history rules, stores the new hash and signs the user out everywhere by
+/// revoking their sessions and setting a token cutoff. Issuing a fresh
+/// session for the device that made the change is left to the caller.
+pub struct PasswordChangeWorkflow {
+    config: PasswordChangeConfig,
+    user_repository: Arc<UserRepository>,
+    session_manager: Arc<SessionManager>,
+    job_queue: Arc<dyn JobQueue>,
+    audit_logger: Option<AuditLogger>,
+    password_hasher: Arc<PasswordHasher>,
+    redis: ConnectionManager,
+    email_templates: Option<Arc<EmailTemplateResolver>>,
+}
+
+impl PasswordChangeWorkflow {
+    pub fn new(
+        config: PasswordChangeConfig,
+        user_repository: Arc<UserRepository>,
+        session_manager: Arc<SessionManager>,
+        job_queue: Arc<dyn JobQueue>,
+        audit_logger: Option<AuditLogger>,
+        password_hasher: Arc<PasswordHasher>,
+        redis: ConnectionManager,
+    ) -> Self {
+        Self {
+            config,
+            user_repository,
+            session_manager,
+            job_queue,
+            audit_logger,
+            password_hasher,
+            redis,
+            email_templates: None,
+        }
+    }
+
+    /// Send emails in the tenant's wording where it overrides the built-in templates
+    pub fn with_email_templates(mut self, email_templates: Arc<EmailTemplateResolver>) -> Self {
+        self.email_templates = Some(email_templates);
+        self
+    }
+
+    /// Change the password of a logged-in user
+    pub async fn change_password(
+        &self,
+        tenant: &TenantContext,
+        user_id: Uuid,
+        request: PasswordChangeRequest,
+    ) -> Result<User> {
+        info!(
+            tenant_id = %tenant.tenant_id.0,
+            user_id = %user_id,
+            client_ip = ?request.client_ip,
+            "Processing password change"
+        );
+
+        self.check_rate_limit(tenant, user_id, &request).await?;
+
+        match self.apply_change(tenant, user_id, &request).await {
+            Ok(user) => Ok(user),
+            Err(e) => {
+                self.audit_failure(user_id, &request, &e).await?;
+                Err(e)
+            }
+        }
+    }
+
+    async fn apply_change(
+        &self,
+        tenant: &TenantContext,
+        user_id: Uuid,
+        request: &PasswordChangeRequest,
+    ) -> Result<User> {
+        let user = self.user_repository.find_by_id(tenant, user_id).await?
+            .ok_or_else(|| Error::new(ErrorCode::ResourceNotFound, "User not found"))?;
+
+        // Invited users have never set a password; they finish the invitation instead
+        let current_hash = user.password_hash.clone().ok_or_else(|| {
+            Error::new(
+                ErrorCode::BusinessRuleViolation,
+                "No password has been set for this account",
+            )
+            .with_details("Complete the invitation or email verification flow to set your first password")
+        })?;
+
+        if !self.password_hasher.verify_password(&request.current_password, &current_hash)? {
+            return Err(Error::new(
+                ErrorCode::InvalidCredentials,
+                "Current password is incorrect"
+            ));
+        }
+
+        if request.new_password != request.confirm_password {
+            return Err(Error::new(
+                ErrorCode::ValidationFailed,
+                "Passwords do not match"
+            ));
+        }
+
+        let policy = PasswordPolicy::for_tenant(&self.user_repository, tenant, &self.config.default_policy).await?;
+        policy.validate(&request.new_password)?;
+
+        let previous_hashes = self.user_repository
+            .get_password_history(tenant, user.id, policy.history_count)
+            .await?;
+        policy.check_history(&self.password_hasher, &request.new_password, Some(&current_hash), &previous_hashes)?;
+
+        let password_hash = self.password_hasher.hash_password(&request.new_password)?;
+        self.user_repository.update_password(tenant, user.id, &password_hash).await?;
+        self.user_repository
+            .record_password_history(tenant, user.id, &current_hash, policy.history_count)
+            .await?;
+
+        // Sign out every other device: drop the sessions and reject every
+        // access or refresh token issued before this moment
+        let revoked_sessions = self.session_manager
+            .invalidate_user_sessions(tenant, user.id, SessionState::Revoked)
+            .await?;
+        let cutoff_key = tokens_valid_after_key(&tenant.tenant_id.0.to_string(), &user.id.to_string());
+        let mut redis = self.redis.clone();
+        redis.set_ex::<_, _, ()>(&cutoff_key, Utc::now().timestamp(), self.config.token_cutoff_ttl_seconds).await?;
+
+        self.send_password_changed_email(tenant, &user, request.client_ip.clone()).await?;
+
+        if let Some(audit_logger) = &self.audit_logger {
+            audit_logger.log_event(
+                AuditEvent::builder(
+                    EventType::Custom("PASSWORD_CHANGED".to_string()),
+                    "Password changed by user"
+                )
+                .severity(EventSeverity::Info)
+                .outcome(EventOutcome::Success)
+                .resource("user", user.id.to_string())
+                .metadata("email".to_string(), serde_json::Value::String(user.email.clone()))
+                .metadata("revoked_sessions".to_string(),
+                    serde_json::Value::Number(revoked_sessions.into()))
+                .metadata("client_ip".to_string(),
+                    serde_json::Value::String(request.client_ip.clone().unwrap_or_default()))
+                .metadata("user_agent".to_string(),
+                    serde_json::Value::String(request.user_agent.clone().unwrap_or_default()))
+                .build()
+            ).await?;
+        }
+
+        info!(
+            user_id = %user.id,
+            revoked_sessions = revoked_sessions,
+            "Password changed successfully"
+        );
+
+        Ok(user)
+    }
+
+    // Private helper methods
+
+    async fn check_rate_limit(
+        &self,
+        tenant: &TenantContext,
+        user_id: Uuid,
+        request: &PasswordChangeRequest,
+    ) -> Result<()> {
+        let key = format!("password_change_attempts:{}:{}", tenant.tenant_id.0, user_id);
+        let mut redis = self.redis.clone();
+        let count: u32 = redis.incr(&key, 1).await?;
+        if count == 1 {
+            redis.expire::<_, ()>(&key, 3600).await?;
+        }
+
+        if count > self.config.max_attempts_per_hour {
+            warn!(
+                tenant_id = %tenant.tenant_id.0,
+                user_id = %user_id,
+                count = count,
+                limit = self.config.max_attempts_per_hour,
+                "Password change rate limit exceeded"
+            );
+
+            if let Some(audit_logger) = &self.audit_logger {
+                audit_logger.log_event(
+                    AuditEvent::builder(
+                        EventType::SecurityPolicyViolation,
+                        "Password change rate limit exceeded"
+                    )
+                    .severity(EventSeverity::Warning)
+                    .outcome(EventOutcome::Failure)
+                    .resource("user", user_id.to_string())
+                    .metadata("attempt_count".to_string(), serde_json::Value::Number(count.into()))
+                    .metadata("limit".to_string(), serde_json::Value::Number(self.config.max_attempts_per_hour.into()))
+                    .metadata("client_ip".to_string(),
+                        serde_json::Value::String(request.client_ip.clone().unwrap_or_default()))
+                    .build()
+                ).await?;
+            }
+
+            return Err(Error::new(
+                ErrorCode::RateLimitExceeded,
+                "Too many password change attempts. Please try again later."
+            ));
+        }
+
+        Ok(())
+    }
+
+    async fn audit_failure(
+        &self,
+        user_id: Uuid,
+        request: &PasswordChangeRequest,
+        error: &Error,
+    ) -> Result<()> {
+        if let Some(audit_logger) = &self.audit_logger {
+            audit_logger.log_event(
+                AuditEvent::builder(
+                    EventType::Custom("PASSWORD_CHANGE_FAILED".to_string()),
+                    "Password change failed"
+                )
+                .severity(EventSeverity::Warning)
+                .outcome(EventOutcome::Failure)
+                .resource("user", user_id.to_string())
+                .metadata("reason".to_string(), serde_json::Value::String(error.message.clone()))
+                .metadata("error_code".to_string(), serde_json::Value::String(format!("{:?}", error.code)))
+                .metadata("client_ip".to_string(),
+                    serde_json::Value::String(request.client_ip.clone().unwrap_or_default()))
+                .build()
+            ).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn send_password_changed_email(
+        &self,
+        tenant: &TenantContext,
+        user: &User,
+        client_ip: Option<String>,
+    ) -> Result<()> {
+        let email_template = PasswordChangedEmailTemplate {
+            user_name: format!("{} {}", user.first_name.clone().unwrap_or_default(), user.last_name.clone().unwrap_or_default()),
+            company_name: self.config.company_name.clone(),
+            support_url: self.config.support_url.clone(),
+            changed_at: Utc::now(),
+            source_ip: client_ip,
+        };
+
+        let email_job = email_job(
+            self.email_templates.as_deref(),
+            &user.email,
+            &email_template,
+            tenant.tenant_id.0,
+            Some(user.id.to_string()),
+        ).await.with_metadata("workflow".to_string(), serde_json::Value::String("password_change".to_string()));
+
+        let queued_job = erp_core::jobs::types::QueuedJob::new(&email_job)?;
+        self.job_queue.enqueue(queued_job).await?;
+
+        debug!(
+            user_id = %user.id,
+            email = %user.email,
+            "Password changed notification queued"
+        );
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use erp_core::config::SecurityConfig;
+    use erp_core::security::is_token_superseded;
+
+    fn hasher() -> PasswordHasher {
+        PasswordHasher::new(&SecurityConfig {
+            argon2_memory_cost: 1024,
+            argon2_time_cost: 1,
+            argon2_parallelism: 1,
+            aes_encryption_key: "test-encryption-key-exactly-32-ch".to_string(),
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_wrong_current_password_is_rejected() {
+        let hasher = hasher();
+        let stored = hasher.hash_password("Current-Passw0rd").unwrap();
+
+        assert!(!hasher.verify_password("Wrong-Passw0rd", &stored).unwrap());
+        assert!(hasher.verify_password("Current-Passw0rd", &stored).unwrap());
+    }
+
+    #[test]
+    fn test_policy_violation_is_rejected() {
+        let policy = PasswordChangeConfig::default().default_policy;
+
+        let error = policy.validate("weakpass").unwrap_err();
+        assert_eq!(error.code, ErrorCode::ValidationFailed);
+        assert!(policy.validate("Str0ng-Passw0rd").is_ok());
+    }
+
+    #[test]
+    fn test_reusing_a_recent_password_is_rejected() {
+        let hasher = hasher();
+        let policy = PasswordChangeConfig::default().default_policy;
+        let current = hasher.hash_password("Current-Passw0rd").unwrap();
+        let previous = vec![hasher.hash_password("Previous-Passw0rd").unwrap()];
+
+        let error = policy
+            .check_history(&hasher, "Previous-Passw0rd", Some(&current), &previous)
+            .unwrap_err();
+        assert_eq!(error.code, ErrorCode::ValidationFailed);
+        assert!(policy.check_history(&hasher, "Current-Passw0rd", Some(&current), &previous).is_err());
+        assert!(policy.check_history(&hasher, "Fresh-Passw0rd", Some(&current), &previous).is_ok());
+    }
+
+    #[test]
+    fn test_tokens_from_other_sessions_are_superseded() {
+        let changed_at = Utc::now().timestamp();
+
+        // Tokens held by other devices were issued before the change
+        assert!(is_token_superseded(changed_at - 60, Some(changed_at)));
+        // The pair issued to the device that made the change stays valid
+        assert!(!is_token_superseded(changed_at, Some(changed_at)));
+        assert!(!is_token_superseded(changed_at - 60, None));
+    }
+}
@@ -1,5 +1,6 @@
-use crate::email::{EmailJobData, PasswordResetEmailTemplate};
+use crate::email::{email_job, EmailTemplateResolver, PasswordResetEmailTemplate};
 use crate::models::User;
+use crate::password_policy::PasswordPolicy;
 use crate::repository::UserRepository;
 use crate::tokens::{TokenManager, TokenPurpose};
 use erp_core::{
@@ -24,6 +25,8 @@ pub struct PasswordResetConfig {
     pub min_password_length: u8,
     /// Require password complexity
     pub require_password_complexity: bool,
+    /// Previous passwords that may not be reused
+    pub password_history_count: u32,
     /// Company name for email templates
     pub company_name: String,
     /// Base URL for reset links
@@ -37,6 +40,7 @@ impl Default for PasswordResetConfig {
             max_requests_per_hour: 3,
             min_password_length: 8,
             require_password_complexity: true,
+            password_history_count: 5,
             company_name: "ERP System".to_string(),
             base_url: "https://localhost:3000".to_string(),
         }
@@ -69,6 +73,7 @@ pub struct PasswordResetWorkflow {
     audit_logger: Option<AuditLogger>,
     password_hasher: Arc<PasswordHasher>,
     db: DatabasePool,
+    email_templates: Option<Arc<EmailTemplateResolver>>,
 }
 
 impl PasswordResetWorkflow {
@@ -89,9 +94,16 @@ impl PasswordResetWorkflow {
             audit_logger,
             password_hasher,
             db,
+            email_templates: None,
         }
     }
 
+    /// Send emails in the tenant's wording where it overrides the built-in templates
+    pub fn with_email_templates(mut self, email_templates: Arc<EmailTemplateResolver>) -> Self {
+        self.email_templates = Some(email_templates);
+        self
+    }
+
     /// Initiate password reset process
     pub async fn request_password_reset(
         &self,
@@ -184,7 +196,24 @@ impl PasswordResetWorkflow {
             ));
         }
 
-        self.validate_password_strength(&confirmation.new_password)?;
+        let policy = PasswordPolicy::for_tenant(&self.user_repository, tenant, &self.default_policy()).await?;
+        policy.validate(&confirmation.new_password)?;
+
+        // Check password history before the token is consumed so a rejected
+        // password does not cost the user their reset link
+        if let Some(token_data) = self.token_manager.get_token(tenant, &confirmation.token, TokenPurpose::PasswordReset).await? {
+            if let Some(user) = self.user_repository.find_by_id(tenant, token_data.user_id).await? {
+                let previous_hashes = self.user_repository
+                    .get_password_history(tenant, user.id, policy.history_count)
+                    .await?;
+                policy.check_history(
+                    &self.password_hasher,
+                    &confirmation.new_password,
+                    user.password_hash.as_deref(),
+                    &previous_hashes,
+                )?;
+            }
+        }
 
         // Validate and consume token
         let token_data = self.token_manager.validate_token(
@@ -203,6 +232,11 @@ impl PasswordResetWorkflow {
 
         // Update user password
         self.user_repository.update_password(tenant, user.id, &password_hash).await?;
+        if let Some(previous_hash) = &user.password_hash {
+            self.user_repository
+                .record_password_history(tenant, user.id, previous_hash, policy.history_count)
+                .await?;
+        }
 
         // Invalidate all existing password reset tokens for this user
         let invalidated_count = self.token_manager.invalidate_user_tokens(
@@ -372,12 +406,13 @@ impl PasswordResetWorkflow {
         };
 
         // Create email job
-        let email_job = EmailJobData::from_template(
+        let email_job = email_job(
+            self.email_templates.as_deref(),
             &user.email,
             &email_template,
-            Some(tenant.tenant_id.0.to_string()),
+            tenant.tenant_id.0,
             Some(user.id.to_string()),
-        ).with_metadata("workflow".to_string(), serde_json::Value::String("password_reset".to_string()));
+        ).await.with_metadata("workflow".to_string(), serde_json::Value::String("password_reset".to_string()));
 
         // Queue email for background sending
         // Create a proper queued job from the serializable job
@@ -394,29 +429,12 @@ impl PasswordResetWorkflow {
         Ok(())
     }
 
-    fn validate_password_strength(&self, password: &str) -> Result<()> {
-        if password.len() < self.config.min_password_length as usize {
-            return Err(Error::new(
-                ErrorCode::ValidationFailed,
-                format!("Password must be at least {} characters long", self.config.min_password_length)
-            ));
-        }
-
-        if self.config.require_password_complexity {
-            let has_upper = password.chars().any(|c| c.is_uppercase());
-            let has_lower = password.chars().any(|c| c.is_lowercase());
-            let has_digit = password.chars().any(|c| c.is_numeric());
-            let has_special = password.chars().any(|c| "!@#$%^&*()_+-=[]{}|;:,.<>?".contains(c));
-
-            if !has_upper || !has_lower || !has_digit || !has_special {
-                return Err(Error::new(
-                    ErrorCode::ValidationFailed,
-                    "Password must contain uppercase, lowercase, number, and special character"
-                ));
-            }
+    fn default_policy(&self) -> PasswordPolicy {
+        PasswordPolicy {
+            min_length: self.config.min_password_length as usize,
+            require_complexity: self.config.require_password_complexity,
+            history_count: self.config.password_history_count,
         }
-
-        Ok(())
     }
 
     fn hash_password(&self, password: &str) -> Result<String> {
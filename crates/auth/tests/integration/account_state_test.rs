@@ -0,0 +1,223 @@
+use super::common::{TestContext, init_test_logging};
+use erp_auth::dto::{InviteUserRequest, LoginRequest, RegisterRequest, UpdateUserRequest};
+use erp_auth::models::AccountState;
+use erp_auth::AuthRepository;
+use erp_core::{Error, ErrorCode, TenantContext, TenantId};
+use uuid::Uuid;
+
+const PASSWORD: &str = "StatePassword123!";
+
+async fn register(ctx: &TestContext, email: &str) -> (TenantContext, Uuid) {
+    let registration = ctx.auth_service
+        .register_tenant(RegisterRequest {
+            company_name: format!("Account State {}", Uuid::new_v4()),
+            email: email.to_string(),
+            password: PASSWORD.to_string(),
+            first_name: "State".to_string(),
+            last_name: "Admin".to_string(),
+        })
+        .await
+        .expect("Registration should succeed");
+
+    let tenant = AuthRepository::new(ctx.db.clone())
+        .get_tenant_by_id(registration.tenant_id)
+        .await
+        .expect("Tenant lookup should succeed")
+        .expect("Tenant should exist");
+
+    (
+        TenantContext {
+            tenant_id: TenantId(tenant.id),
+            schema_name: tenant.schema_name,
+        },
+        registration.user_id,
+    )
+}
+
+async fn login_error(ctx: &TestContext, tenant: &TenantContext, email: &str) -> Error {
+    ctx.auth_service
+        .login(
+            tenant.tenant_id.0,
+            LoginRequest { email: email.to_string(), password: PASSWORD.to_string() },
+            None,
+            None,
+        )
+        .await
+        .expect_err("Login should be refused")
+}
+
+async fn invite(ctx: &TestContext, tenant: &TenantContext, email: &str) -> erp_core::Result<Uuid> {
+    ctx.auth_service
+        .invite_user(tenant, InviteUserRequest {
+            email: email.to_string(),
+            first_name: Some("Invited".to_string()),
+            last_name: Some("User".to_string()),
+            role_ids: vec![],
+        }, None)
+        .await
+        .map(|user| user.id)
+}
+
+async fn set_seat_limit(ctx: &TestContext, tenant: &TenantContext, limit: Option<i32>) {
+    sqlx::query("UPDATE public.tenants SET seat_limit = $1 WHERE id = $2")
+        .bind(limit)
+        .bind(tenant.tenant_id.0)
+        .execute(&ctx.db.main_pool)
+        .await
+        .expect("Seat limit should be set");
+}
+
+#[tokio::test]
+async fn test_login_refusal_looks_the_same_in_every_state() {
+    init_test_logging();
+    let ctx = TestContext::new().await;
+    let repository = AuthRepository::new(ctx.db.clone());
+
+    let unknown = {
+        let (tenant, _) = register(&ctx, "unknown-state@test.com").await;
+        login_error(&ctx, &tenant, "nobody@test.com").await
+    };
+    assert!(!unknown.context.metadata.contains_key("login_denial"));
+
+    let cases = [
+        (AccountState::Deactivated, "ACCOUNT_DEACTIVATED"),
+        (AccountState::Locked, "ACCOUNT_LOCKED"),
+        (AccountState::SoftDeleted, "ACCOUNT_DELETED"),
+    ];
+    for (state, denial) in cases {
+        let email = format!("{}@test.com", state);
+        let (tenant, user_id) = register(&ctx, &email).await;
+        match state {
+            AccountState::Deactivated => {
+                ctx.auth_service
+                    .update_user(&tenant, user_id, UpdateUserRequest {
+                        first_name: None,
+                        last_name: None,
+                        is_active: Some(false),
+                    })
+                    .await
+                    .expect("Deactivation should succeed");
+            }
+            AccountState::Locked => {
+                repository
+                    .lock_user(&tenant, user_id, chrono::Utc::now() + chrono::Duration::minutes(15))
+                    .await
+                    .expect("Lock should succeed");
+            }
+            _ => {
+                repository.soft_delete_user(&tenant, user_id).await.expect("Delete should succeed");
+            }
+        }
+
+        let user = ctx.auth_service.get_user(&tenant, user_id).await.expect("Admin view should show the user");
+        assert_eq!(user.state, state);
+
+        // The caller learns nothing beyond "Invalid credentials"
+        let error = login_error(&ctx, &tenant, &email).await;
+        assert_eq!((error.code, error.message.as_str()), (unknown.code, unknown.message.as_str()));
+        assert_eq!(error.context.metadata["login_denial"], denial);
+        assert!(!error.to_api_response().to_string().contains(denial));
+    }
+
+    ctx.cleanup().await;
+}
+
+#[tokio::test]
+async fn test_reactivate_soft_deleted_user() {
+    init_test_logging();
+    let ctx = TestContext::new().await;
+    let (tenant, _) = register(&ctx, "reactivate-admin@test.com").await;
+    let user_id = invite(&ctx, &tenant, "reactivate-me@test.com").await.expect("Invitation should succeed");
+
+    ctx.auth_service.delete_user(&tenant, user_id).await.expect("Delete should succeed");
+    let deleted = ctx.auth_service
+        .list_users(&tenant, 0, 100, Some(AccountState::SoftDeleted))
+        .await
+        .expect("Listing should succeed");
+    assert_eq!(deleted.iter().map(|user| user.id).collect::<Vec<_>>(), vec![user_id]);
+    assert!(deleted[0].deleted_at.is_some());
+
+    let user = ctx.auth_service
+        .reactivate_user(&tenant, user_id)
+        .await
+        .expect("Reactivation should succeed");
+    assert_eq!(user.state, AccountState::Active);
+    assert!(user.deleted_at.is_none());
+    // Never verified before deletion, so verification is required again
+    assert!(!user.email_verified);
+
+    assert!(ctx.auth_service
+        .list_users(&tenant, 0, 100, Some(AccountState::SoftDeleted))
+        .await
+        .expect("Listing should succeed")
+        .is_empty());
+
+    let error = ctx.auth_service
+        .reactivate_user(&tenant, user_id)
+        .await
+        .expect_err("An active user cannot be reactivated");
+    assert_eq!(error.code, ErrorCode::ConflictError);
+
+    ctx.cleanup().await;
+}
+
+#[tokio::test]
+async fn test_reactivation_respects_seat_limit() {
+    init_test_logging();
+    let ctx = TestContext::new().await;
+    let (tenant, _) = register(&ctx, "seats-admin@test.com").await;
+    let deleted_id = invite(&ctx, &tenant, "seats-deleted@test.com").await.expect("Invitation should succeed");
+    let deactivated_id = invite(&ctx, &tenant, "seats-deactivated@test.com").await.expect("Invitation should succeed");
+
+    ctx.auth_service.delete_user(&tenant, deleted_id).await.expect("Delete should succeed");
+    ctx.auth_service
+        .update_user(&tenant, deactivated_id, UpdateUserRequest {
+            first_name: None,
+            last_name: None,
+            is_active: Some(false),
+        })
+        .await
+        .expect("Deactivation should succeed");
+
+    // Only the admin holds a seat now, and it is the last one
+    set_seat_limit(&ctx, &tenant, Some(1)).await;
+
+    let error = ctx.auth_service
+        .reactivate_user(&tenant, deleted_id)
+        .await
+        .expect_err("No seat is free");
+    assert_eq!(error.code, ErrorCode::ResourceQuotaExceeded);
+
+    let error = ctx.auth_service
+        .update_user(&tenant, deactivated_id, UpdateUserRequest {
+            first_name: None,
+            last_name: None,
+            is_active: Some(true),
+        })
+        .await
+        .expect_err("Switching a user back on needs a seat as well");
+    assert_eq!(error.code, ErrorCode::ResourceQuotaExceeded);
+
+    let error = invite(&ctx, &tenant, "seats-new@test.com").await.expect_err("Invitations need a seat");
+    assert_eq!(error.code, ErrorCode::ResourceQuotaExceeded);
+
+    let user = ctx.auth_service.get_user(&tenant, deleted_id).await.expect("User should exist");
+    assert_eq!(user.state, AccountState::SoftDeleted);
+
+    set_seat_limit(&ctx, &tenant, Some(2)).await;
+    let user = ctx.auth_service
+        .reactivate_user(&tenant, deleted_id)
+        .await
+        .expect("A seat is free again");
+    assert_eq!(user.state, AccountState::Active);
+
+    assert!(ctx.auth_service.reactivate_user(&tenant, deactivated_id).await.is_err());
+
+    set_seat_limit(&ctx, &tenant, None).await;
+    ctx.auth_service
+        .reactivate_user(&tenant, deactivated_id)
+        .await
+        .expect("Without a limit every user can come back");
+
+    ctx.cleanup().await;
+}
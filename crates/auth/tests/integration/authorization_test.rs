@@ -487,6 +487,8 @@ async fn generate_test_jwt(ctx: &TestContext, user_id: Uuid, permissions: Vec<St
         iat: chrono::Utc::now().timestamp(),
         jti: Uuid::new_v4().to_string(),
         impersonator_id: None,
+        portal: None,
+        sid: None,
     };
     
     let secret = b"test_jwt_secret_key_for_testing_only";
@@ -507,6 +509,8 @@ async fn generate_expired_jwt(ctx: &TestContext, user_id: Uuid, permissions: Vec
         iat: (chrono::Utc::now() - chrono::Duration::hours(2)).timestamp(),
         jti: Uuid::new_v4().to_string(),
         impersonator_id: None,
+        portal: None,
+        sid: None,
     };
     
     let secret = b"test_jwt_secret_key_for_testing_only";
@@ -527,6 +531,8 @@ async fn generate_impersonation_jwt(ctx: &TestContext, user_id: Uuid, impersonat
         iat: chrono::Utc::now().timestamp(),
         jti: Uuid::new_v4().to_string(),
         impersonator_id: Some(impersonator_id.to_string()),
+        portal: None,
+        sid: None,
     };
     
     let secret = b"test_jwt_secret_key_for_testing_only";
@@ -559,6 +565,7 @@ async fn create_auth_state(ctx: &TestContext) -> AuthState {
         jwt_service: ctx.auth_service.jwt_service().clone(),
         db: Arc::new(ctx.db.clone()),
         redis: ctx.redis.clone(),
+        tenants: ctx.auth_service.tenant_resolver(),
     }
 }
 
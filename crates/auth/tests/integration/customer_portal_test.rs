@@ -0,0 +1,139 @@
+use super::common::{TestContext, init_test_logging};
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+    middleware::from_fn_with_state,
+    routing::get,
+    Router,
+};
+use erp_auth::{
+    dto::{AcceptPortalInvitationRequest, InvitePortalUserRequest, LoginRequest, RegisterRequest},
+    middleware::{auth_middleware, portal_guard, AuthState},
+    service::LoginOrTwoFactorResponse,
+};
+use erp_core::{RequestContext, TenantContext, TenantId};
+use std::sync::Arc;
+use tower::ServiceExt;
+use uuid::Uuid;
+
+fn test_router(ctx: &TestContext) -> Router {
+    let auth_state = AuthState {
+        jwt_service: ctx.auth_service.jwt_service(),
+        db: Arc::new(ctx.db.clone()),
+        redis: ctx.redis.clone(),
+        tenants: ctx.auth_service.tenant_resolver(),
+    };
+
+    async fn scoped_customer(context: RequestContext) -> String {
+        context.portal.map(|scope| scope.customer_id.to_string()).unwrap_or_default()
+    }
+
+    Router::new()
+        .route("/customers/:id", get(scoped_customer)
+            .layer(from_fn_with_state(auth_state.clone(), auth_middleware)))
+        .route("/users", get(|| async { "users" })
+            .layer(from_fn_with_state(auth_state.clone(), auth_middleware)))
+        .route("/roles", get(|| async { "roles" })
+            .layer(from_fn_with_state(auth_state, portal_guard)))
+}
+
+async fn call(app: &Router, path: &str, token: &str) -> (StatusCode, String) {
+    let request = Request::builder()
+        .uri(path)
+        .header("Authorization", format!("Bearer {}", token))
+        .body(Body::empty())
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, String::from_utf8_lossy(&body).to_string())
+}
+
+#[tokio::test]
+async fn test_portal_account_lifecycle() {
+    init_test_logging();
+    let ctx = TestContext::new().await;
+
+    let registration = ctx.auth_service
+        .register_tenant(RegisterRequest {
+            company_name: "Portal Test Company".to_string(),
+            email: "portal-admin@example.com".to_string(),
+            password: "PortalAdmin123!".to_string(),
+            first_name: "Portal".to_string(),
+            last_name: "Admin".to_string(),
+        })
+        .await
+        .expect("Registration should succeed");
+    let tenant = ctx.auth_service
+        .repository()
+        .get_tenant_by_id(registration.tenant_id)
+        .await
+        .unwrap()
+        .unwrap();
+    let tenant_context = TenantContext {
+        tenant_id: TenantId(tenant.id),
+        schema_name: tenant.schema_name,
+    };
+
+    let customer_id = Uuid::new_v4();
+    let (account, invitation) = ctx.auth_service
+        .invite_portal_user(&tenant_context, InvitePortalUserRequest {
+            customer_id,
+            contact_id: Some(Uuid::new_v4()),
+            email: "buyer@customer.example.com".to_string(),
+            first_name: Some("Pat".to_string()),
+            last_name: Some("Buyer".to_string()),
+            include_children: true,
+        }, None)
+        .await
+        .expect("Invitation should succeed");
+    assert_eq!(account.status, "invited");
+
+    // An invitation only works for its own customer
+    let wrong_customer = ctx.auth_service
+        .accept_portal_invitation(&tenant_context, Uuid::new_v4(), AcceptPortalInvitationRequest {
+            token: invitation.clone(),
+            password: "CustomerPortal123!".to_string(),
+        })
+        .await;
+    assert!(wrong_customer.is_err());
+
+    let account = ctx.auth_service
+        .accept_portal_invitation(&tenant_context, customer_id, AcceptPortalInvitationRequest {
+            token: invitation.clone(),
+            password: "CustomerPortal123!".to_string(),
+        })
+        .await
+        .expect("Acceptance should succeed");
+    assert!(account.is_active());
+    let user = ctx.auth_service.repository().get_user_by_id(&tenant_context, account.user_id).await.unwrap().unwrap();
+    assert!(user.email_verified_at.is_some());
+
+    let login = || ctx.auth_service.login(registration.tenant_id, LoginRequest {
+        email: "buyer@customer.example.com".to_string(),
+        password: "CustomerPortal123!".to_string(),
+    }, None, None);
+    let token = match login().await.expect("Portal login should succeed") {
+        LoginOrTwoFactorResponse::Success(response) => response.access_token,
+        LoginOrTwoFactorResponse::TwoFactorRequired(_) => panic!("Portal accounts have no 2FA yet"),
+    };
+    let claims = ctx.auth_service.jwt_service().verify_access_token(&token).unwrap();
+    assert_eq!(claims.roles, vec!["customer_portal".to_string()]);
+    assert_eq!(claims.portal.map(|scope| (scope.customer_id, scope.include_children)), Some((customer_id, true)));
+
+    // Portal routes carry the scope, admin and user-management routes are closed
+    let app = test_router(&ctx);
+    assert_eq!(call(&app, &format!("/customers/{}", customer_id), &token).await, (StatusCode::OK, customer_id.to_string()));
+    assert_eq!(call(&app, "/users", &token).await.0, StatusCode::FORBIDDEN);
+    assert_eq!(call(&app, "/roles", &token).await.0, StatusCode::FORBIDDEN);
+
+    // Revocation ends the sessions: the token in use stops working and no new one is issued
+    ctx.auth_service
+        .revoke_portal_user(&tenant_context, customer_id, account.user_id, None)
+        .await
+        .expect("Revocation should succeed");
+    assert_eq!(call(&app, &format!("/customers/{}", customer_id), &token).await.0, StatusCode::UNAUTHORIZED);
+    assert!(login().await.is_err());
+
+    ctx.cleanup().await;
+}
@@ -0,0 +1,169 @@
+use super::common::{TestContext, init_test_logging};
+use erp_auth::dto::{AcceptInvitationRequest, InviteUserRequest, LoginRequest, RegisterRequest};
+use erp_auth::AuthRepository;
+use erp_core::{ErrorCode, TenantContext, TenantId};
+use uuid::Uuid;
+
+const PASSWORD: &str = "InvitedPassword123!";
+
+async fn register(ctx: &TestContext, email: &str) -> (TenantContext, Uuid) {
+    let registration = ctx.auth_service
+        .register_tenant(RegisterRequest {
+            company_name: format!("Invitation {}", Uuid::new_v4()),
+            email: email.to_string(),
+            password: "InvitingAdmin123!".to_string(),
+            first_name: "Inviting".to_string(),
+            last_name: "Admin".to_string(),
+        })
+        .await
+        .expect("Registration should succeed");
+
+    let tenant = AuthRepository::new(ctx.db.clone())
+        .get_tenant_by_id(registration.tenant_id)
+        .await
+        .expect("Tenant lookup should succeed")
+        .expect("Tenant should exist");
+
+    (
+        TenantContext {
+            tenant_id: TenantId(tenant.id),
+            schema_name: tenant.schema_name,
+        },
+        registration.user_id,
+    )
+}
+
+async fn invite(ctx: &TestContext, tenant: &TenantContext, email: &str, invited_by: Uuid) -> Uuid {
+    ctx.auth_service
+        .invite_user(tenant, InviteUserRequest {
+            email: email.to_string(),
+            first_name: Some("Invited".to_string()),
+            last_name: Some("User".to_string()),
+            role_ids: vec![],
+        }, Some(invited_by))
+        .await
+        .expect("Invitation should succeed")
+        .id
+}
+
+/// The open invitation token of a user, as the email link carries it
+async fn open_token(ctx: &TestContext, tenant: &TenantContext, user_id: Uuid) -> String {
+    let pool = ctx.db.get_tenant_pool(tenant).await.unwrap();
+    sqlx::query_scalar(
+        "SELECT token FROM verification_tokens
+         WHERE user_id = $1 AND purpose = 'invitation' AND used = false AND expires_at > NOW()"
+    )
+    .bind(user_id)
+    .fetch_one(pool.get())
+    .await
+    .expect("User should have an open invitation")
+}
+
+fn acceptance(password: &str) -> AcceptInvitationRequest {
+    AcceptInvitationRequest {
+        password: password.to_string(),
+        confirm_password: password.to_string(),
+    }
+}
+
+#[tokio::test]
+async fn test_invitation_accepted_once() {
+    init_test_logging();
+    let ctx = TestContext::new().await;
+    let (tenant, admin_id) = register(&ctx, "invite-admin@test.com").await;
+
+    let user_id = invite(&ctx, &tenant, "invitee@test.com", admin_id).await;
+    let token = open_token(&ctx, &tenant, user_id).await;
+
+    let invitation = ctx.auth_service.invitation(&tenant, &token).await.expect("Invitation should be open");
+    assert_eq!(invitation.email, "invitee@test.com");
+    assert_eq!(invitation.inviter_name.as_deref(), Some("Inviting Admin"));
+    assert!(invitation.tenant_name.starts_with("Invitation "));
+
+    // A password the policy refuses leaves the invitation open
+    let weak = ctx.auth_service.accept_invitation(&tenant, &token, acceptance("weak"), None).await;
+    assert_eq!(weak.unwrap_err().code, ErrorCode::ValidationFailed);
+
+    let user = ctx.auth_service
+        .accept_invitation(&tenant, &token, acceptance(PASSWORD), Some("203.0.113.7".to_string()))
+        .await
+        .expect("Acceptance should succeed");
+    assert!(user.email_verified);
+    assert!(user.is_active);
+
+    ctx.auth_service
+        .login(tenant.tenant_id.0, LoginRequest {
+            email: "invitee@test.com".to_string(),
+            password: PASSWORD.to_string(),
+        }, None, None)
+        .await
+        .expect("Invited user should be able to sign in");
+
+    // The token is consumed
+    let again = ctx.auth_service.accept_invitation(&tenant, &token, acceptance("AnotherPassword123!"), None).await;
+    assert_eq!(again.unwrap_err().code, ErrorCode::TokenInvalid);
+    assert!(ctx.auth_service.invitation(&tenant, &token).await.is_err());
+}
+
+#[tokio::test]
+async fn test_expired_invitation_refused() {
+    init_test_logging();
+    let ctx = TestContext::new().await;
+    let (tenant, admin_id) = register(&ctx, "expiry-admin@test.com").await;
+
+    let user_id = invite(&ctx, &tenant, "late@test.com", admin_id).await;
+    let token = open_token(&ctx, &tenant, user_id).await;
+
+    let pool = ctx.db.get_tenant_pool(&tenant).await.unwrap();
+    sqlx::query("UPDATE verification_tokens SET expires_at = NOW() - INTERVAL '1 hour' WHERE token = $1")
+        .bind(&token)
+        .execute(pool.get())
+        .await
+        .unwrap();
+    let mut redis = ctx.redis.clone();
+    let _: () = redis::cmd("DEL")
+        .arg(format!("token:invitation:{}:{}", tenant.tenant_id.0, token))
+        .query_async(&mut redis)
+        .await
+        .unwrap();
+
+    let details = ctx.auth_service.invitation(&tenant, &token).await;
+    assert_eq!(details.unwrap_err().code, ErrorCode::TokenExpired);
+    let accepted = ctx.auth_service.accept_invitation(&tenant, &token, acceptance(PASSWORD), None).await;
+    assert_eq!(accepted.unwrap_err().code, ErrorCode::TokenExpired);
+
+    // The sweep deletes the user, freeing their seat
+    let sweep = ctx.auth_service.sweep_invitations(&tenant).await.unwrap();
+    assert_eq!(sweep.expired_users, vec![user_id]);
+    let user = AuthRepository::new(ctx.db.clone()).get_user_by_id(&tenant, user_id).await.unwrap().unwrap();
+    assert!(user.deleted_at.is_some());
+}
+
+#[tokio::test]
+async fn test_reinvite_invalidates_previous_link() {
+    init_test_logging();
+    let ctx = TestContext::new().await;
+    let (tenant, admin_id) = register(&ctx, "reinvite-admin@test.com").await;
+
+    let user_id = invite(&ctx, &tenant, "reinvited@test.com", admin_id).await;
+    let first = open_token(&ctx, &tenant, user_id).await;
+
+    ctx.auth_service
+        .resend_invitation(&tenant, user_id, Some(admin_id))
+        .await
+        .expect("Re-invite should succeed");
+    let second = open_token(&ctx, &tenant, user_id).await;
+    assert_ne!(first, second);
+
+    let stale = ctx.auth_service.accept_invitation(&tenant, &first, acceptance(PASSWORD), None).await;
+    assert_eq!(stale.unwrap_err().code, ErrorCode::TokenInvalid);
+
+    ctx.auth_service
+        .accept_invitation(&tenant, &second, acceptance(PASSWORD), None)
+        .await
+        .expect("The latest invitation should work");
+
+    // Accepted users have nothing to re-invite to
+    let resent = ctx.auth_service.resend_invitation(&tenant, user_id, Some(admin_id)).await;
+    assert_eq!(resent.unwrap_err().code, ErrorCode::ConflictError);
+}
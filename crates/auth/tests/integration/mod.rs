@@ -2,8 +2,14 @@ pub mod common;
 pub mod registration_test;
 pub mod login_test;
 pub mod password_reset_test;
+pub mod password_change_test;
 pub mod email_verification_test;
 pub mod user_management_test;
 pub mod role_management_test;
 pub mod authorization_test;
-pub mod role_assignment_test;
\ No newline at end of file
+pub mod role_assignment_test;
+pub mod customer_portal_test;
+pub mod account_state_test;
+pub mod session_revocation_test;
+pub mod invitation_test;
+pub mod token_refresh_test;
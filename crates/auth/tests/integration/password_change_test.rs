@@ -0,0 +1,147 @@
+use super::common::{TestContext, init_test_logging};
+use erp_auth::dto::{ChangePasswordRequest, LoginRequest, RegisterRequest};
+use erp_auth::service::LoginOrTwoFactorResponse;
+use erp_auth::AuthRepository;
+use erp_core::{ErrorCode, TenantContext, TenantId};
+use uuid::Uuid;
+
+const ORIGINAL_PASSWORD: &str = "OriginalPassword123!";
+
+async fn register(ctx: &TestContext, email: &str) -> (TenantContext, Uuid) {
+    let registration = ctx.auth_service
+        .register_tenant(RegisterRequest {
+            company_name: format!("Password Change {}", Uuid::new_v4()),
+            email: email.to_string(),
+            password: ORIGINAL_PASSWORD.to_string(),
+            first_name: "Change".to_string(),
+            last_name: "User".to_string(),
+        })
+        .await
+        .expect("Registration should succeed");
+
+    let tenant = AuthRepository::new(ctx.db.clone())
+        .get_tenant_by_id(registration.tenant_id)
+        .await
+        .expect("Tenant lookup should succeed")
+        .expect("Tenant should exist");
+
+    (
+        TenantContext {
+            tenant_id: TenantId(tenant.id),
+            schema_name: tenant.schema_name,
+        },
+        registration.user_id,
+    )
+}
+
+async fn login(ctx: &TestContext, tenant_id: Uuid, email: &str, password: &str) -> String {
+    match ctx.auth_service
+        .login(
+            tenant_id,
+            LoginRequest { email: email.to_string(), password: password.to_string() },
+            None,
+            None,
+        )
+        .await
+        .expect("Login should succeed")
+    {
+        LoginOrTwoFactorResponse::Success(response) => response.refresh_token,
+        LoginOrTwoFactorResponse::TwoFactorRequired(_) => panic!("2FA should not be required"),
+    }
+}
+
+fn change(current: &str, new: &str) -> ChangePasswordRequest {
+    ChangePasswordRequest {
+        current_password: current.to_string(),
+        new_password: new.to_string(),
+        confirm_password: new.to_string(),
+    }
+}
+
+#[tokio::test]
+async fn test_change_password_with_wrong_current_password() {
+    init_test_logging();
+    let ctx = TestContext::new().await;
+    let (tenant, user_id) = register(&ctx, "wrongcurrent@test.com").await;
+
+    let error = ctx.auth_service
+        .change_password(&tenant, user_id, change("NotMyPassword123!", "NewPassword456!"), None, None)
+        .await
+        .expect_err("Wrong current password should be rejected");
+
+    assert_eq!(error.code, ErrorCode::InvalidCredentials);
+
+    ctx.cleanup().await;
+}
+
+#[tokio::test]
+async fn test_change_password_policy_violation() {
+    init_test_logging();
+    let ctx = TestContext::new().await;
+    let (tenant, user_id) = register(&ctx, "policy@test.com").await;
+
+    let error = ctx.auth_service
+        .change_password(&tenant, user_id, change(ORIGINAL_PASSWORD, "weakpassword"), None, None)
+        .await
+        .expect_err("Weak password should be rejected");
+
+    assert_eq!(error.code, ErrorCode::ValidationFailed);
+
+    ctx.cleanup().await;
+}
+
+#[tokio::test]
+async fn test_change_password_history_reuse() {
+    init_test_logging();
+    let ctx = TestContext::new().await;
+    let (tenant, user_id) = register(&ctx, "history@test.com").await;
+
+    ctx.auth_service
+        .change_password(&tenant, user_id, change(ORIGINAL_PASSWORD, "SecondPassword456!"), None, None)
+        .await
+        .expect("First change should succeed");
+
+    let error = ctx.auth_service
+        .change_password(&tenant, user_id, change("SecondPassword456!", ORIGINAL_PASSWORD), None, None)
+        .await
+        .expect_err("Reusing the previous password should be rejected");
+
+    assert_eq!(error.code, ErrorCode::ValidationFailed);
+
+    ctx.cleanup().await;
+}
+
+#[tokio::test]
+async fn test_change_password_invalidates_other_sessions() {
+    init_test_logging();
+    let ctx = TestContext::new().await;
+    let email = "sessions@test.com";
+    let (tenant, user_id) = register(&ctx, email).await;
+
+    let other_device_refresh = login(&ctx, tenant.tenant_id.0, email, ORIGINAL_PASSWORD).await;
+
+    // Tokens carry whole-second issue times; make sure the cutoff lands after them
+    tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+
+    let response = ctx.auth_service
+        .change_password(&tenant, user_id, change(ORIGINAL_PASSWORD, "RotatedPassword789!"), None, None)
+        .await
+        .expect("Password change should succeed");
+
+    let sessions = ctx.auth_service
+        .get_user_sessions(tenant.tenant_id.0, user_id)
+        .await
+        .expect("Session lookup should succeed");
+    assert_eq!(sessions.len(), 1, "Only the session of the changing device should remain");
+
+    assert!(
+        ctx.auth_service.refresh_token(&other_device_refresh).await.is_err(),
+        "Refresh tokens issued before the change should be rejected"
+    );
+    assert!(
+        ctx.auth_service.refresh_token(&response.refresh_token).await.is_ok(),
+        "The token pair issued with the change should stay valid"
+    );
+
+    ctx.cleanup().await;
+}
@@ -0,0 +1,158 @@
+use super::common::{TestContext, init_test_logging};
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+    middleware::from_fn_with_state,
+    routing::get,
+    Router,
+};
+use erp_auth::dto::{LoginRequest, LoginResponse, RegisterRequest};
+use erp_auth::middleware::{auth_middleware, AuthState};
+use erp_auth::service::LoginOrTwoFactorResponse;
+use erp_auth::AuthRepository;
+use erp_core::{ErrorCode, SessionLimit, SessionLimitStrategy};
+use std::sync::Arc;
+use tower::ServiceExt;
+use uuid::Uuid;
+
+const PASSWORD: &str = "RevocationPassword123!";
+
+#[tokio::test]
+async fn test_revoke_sessions_on_tenant_with_generated_schema_name() {
+    init_test_logging();
+    let ctx = TestContext::new().await;
+    let email = "revoked@test.com";
+    let registration = ctx.auth_service
+        .register_tenant(RegisterRequest {
+            company_name: format!("Session Revocation {}", Uuid::new_v4()),
+            email: email.to_string(),
+            password: PASSWORD.to_string(),
+            first_name: "Revoked".to_string(),
+            last_name: "User".to_string(),
+        })
+        .await
+        .expect("Registration should succeed");
+    let (tenant_id, user_id) = (registration.tenant_id, registration.user_id);
+
+    // The schema name is generated, not derived from the tenant id
+    let tenant = AuthRepository::new(ctx.db.clone())
+        .get_tenant_by_id(tenant_id)
+        .await
+        .expect("Tenant lookup should succeed")
+        .expect("Tenant should exist");
+    assert_ne!(tenant.schema_name, format!("tenant_{}", tenant_id));
+    assert_ne!(tenant.schema_name, format!("tenant_{}", tenant_id.to_string().replace('-', "_")));
+    let context = ctx.auth_service.tenant_resolver().resolve(tenant_id).await.expect("Tenant should resolve");
+    assert_eq!(context.schema_name, tenant.schema_name);
+
+    for _ in 0..2 {
+        ctx.auth_service
+            .login(tenant_id, LoginRequest { email: email.to_string(), password: PASSWORD.to_string() }, None, None)
+            .await
+            .expect("Login should succeed");
+    }
+
+    let revoked = ctx.auth_service
+        .revoke_user_sessions(tenant_id, user_id, "regression test")
+        .await
+        .expect("Revocation should succeed");
+    assert_eq!(revoked, 2);
+    let sessions = ctx.auth_service
+        .get_user_sessions(tenant_id, user_id)
+        .await
+        .expect("Session lookup should succeed");
+    assert!(sessions.is_empty(), "No session should survive the revocation");
+
+    ctx.cleanup().await;
+}
+
+#[tokio::test]
+async fn test_session_methods_reject_unknown_tenants() {
+    init_test_logging();
+    let ctx = TestContext::new().await;
+
+    let error = ctx.auth_service
+        .revoke_user_sessions(Uuid::new_v4(), Uuid::new_v4(), "regression test")
+        .await
+        .expect_err("An unknown tenant should not resolve to a guessed schema");
+    assert_eq!(error.code, ErrorCode::ResourceNotFound);
+
+    ctx.cleanup().await;
+}
+
+#[tokio::test]
+async fn test_tokens_of_a_session_evicted_by_the_cap_are_refused() {
+    init_test_logging();
+    let ctx = TestContext::new().await;
+    let email = "evicted@test.com";
+    let registration = ctx.auth_service
+        .register_tenant(RegisterRequest {
+            company_name: format!("Session Eviction {}", Uuid::new_v4()),
+            email: email.to_string(),
+            password: PASSWORD.to_string(),
+            first_name: "Evicted".to_string(),
+            last_name: "User".to_string(),
+        })
+        .await
+        .expect("Registration should succeed");
+    let (tenant_id, user_id) = (registration.tenant_id, registration.user_id);
+    let tenant = ctx.auth_service.tenant_resolver().resolve(tenant_id).await.expect("Tenant should resolve");
+    ctx.auth_service
+        .set_session_limit(&tenant, SessionLimit {
+            max_sessions_per_user: 1,
+            strategy: SessionLimitStrategy::EvictOldest,
+            sliding_sessions: false,
+        }, user_id)
+        .await
+        .expect("Setting the session limit should succeed");
+
+    let login = || async {
+        match ctx.auth_service
+            .login(tenant_id, LoginRequest { email: email.to_string(), password: PASSWORD.to_string() }, None, None)
+            .await
+            .expect("Login should succeed")
+        {
+            LoginOrTwoFactorResponse::Success(response) => response,
+            LoginOrTwoFactorResponse::TwoFactorRequired(_) => panic!("No 2FA is set up"),
+        }
+    };
+    let evicted: LoginResponse = login().await;
+    let current: LoginResponse = login().await;
+
+    let auth_state = AuthState {
+        jwt_service: ctx.auth_service.jwt_service(),
+        db: Arc::new(ctx.db.clone()),
+        redis: ctx.redis.clone(),
+        tenants: ctx.auth_service.tenant_resolver(),
+    };
+    let app = Router::new()
+        .route("/me", get(|| async { "me" }))
+        .layer(from_fn_with_state(auth_state, auth_middleware));
+    let call = |token: String| {
+        let app = app.clone();
+        async move {
+            let request = Request::builder()
+                .uri("/me")
+                .header("Authorization", format!("Bearer {}", token))
+                .body(Body::empty())
+                .unwrap();
+            app.oneshot(request).await.unwrap().status()
+        }
+    };
+
+    // The second login took the only seat: the first device's tokens stop working
+    assert_eq!(call(evicted.access_token.clone()).await, StatusCode::UNAUTHORIZED);
+    let refresh = ctx.auth_service
+        .refresh_token(&evicted.refresh_token)
+        .await
+        .expect_err("The evicted session's refresh token should be refused");
+    assert_eq!(refresh.code, ErrorCode::TokenInvalid);
+
+    assert_eq!(call(current.access_token.clone()).await, StatusCode::OK);
+    ctx.auth_service
+        .refresh_token(&current.refresh_token)
+        .await
+        .expect("The current session should still refresh");
+
+    ctx.cleanup().await;
+}
@@ -0,0 +1,154 @@
+use super::common::{TestContext, init_test_logging};
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+    middleware::from_fn_with_state,
+    response::Response,
+    routing::get,
+    Router,
+};
+use erp_auth::middleware::{
+    auth_middleware, AuthState, REFRESHED_ACCESS_TOKEN_HEADER, TOKEN_EXPIRES_IN_HEADER, TOKEN_REFRESH_ADVISED_HEADER,
+};
+use erp_auth::AuthRepository;
+use erp_core::{JwtClaims, SessionLimit, SessionLimitStrategy, TenantContext, TenantId};
+use std::sync::Arc;
+use tower::ServiceExt;
+use uuid::Uuid;
+
+fn test_router(ctx: &TestContext) -> Router {
+    let auth_state = AuthState {
+        jwt_service: ctx.auth_service.jwt_service(),
+        db: Arc::new(ctx.db.clone()),
+        redis: ctx.redis.clone(),
+        tenants: ctx.auth_service.tenant_resolver(),
+    };
+    Router::new()
+        .route("/protected", get(|| async { "protected_content" }))
+        .layer(from_fn_with_state(auth_state, auth_middleware))
+}
+
+async fn call(app: &Router, token: &str, proto: &str) -> Response {
+    let request = Request::builder()
+        .uri("/protected")
+        .header("Authorization", format!("Bearer {}", token))
+        .header("X-Forwarded-Proto", proto)
+        .body(Body::empty())
+        .unwrap();
+    app.clone().oneshot(request).await.unwrap()
+}
+
+/// A 15 minute access token with `remaining` seconds of it left
+fn token(ctx: &TestContext, remaining: i64, impersonator_id: Option<Uuid>) -> (String, String) {
+    use jsonwebtoken::{encode, EncodingKey, Header};
+
+    let now = chrono::Utc::now().timestamp();
+    let claims = JwtClaims {
+        sub: Uuid::new_v4().to_string(),
+        tenant_id: ctx.tenant_id.to_string(),
+        roles: vec!["test_role".to_string()],
+        permissions: vec!["products:read".to_string()],
+        exp: now + remaining,
+        iat: now + remaining - 900,
+        jti: Uuid::new_v4().to_string(),
+        impersonator_id: impersonator_id.map(|id| id.to_string()),
+        portal: None,
+        sid: None,
+    };
+    let secret = std::env::var("JWT_SECRET").unwrap();
+    let token = encode(&Header::new(jsonwebtoken::Algorithm::HS512), &claims, &EncodingKey::from_secret(secret.as_bytes()))
+        .expect("Failed to generate test JWT");
+    (token, claims.jti)
+}
+
+async fn enable_sliding_sessions(ctx: &TestContext) {
+    let tenant = TenantContext { tenant_id: TenantId(ctx.tenant_id), schema_name: String::new() };
+    let limit = SessionLimit { max_sessions_per_user: 10, strategy: SessionLimitStrategy::EvictOldest, sliding_sessions: true };
+    AuthRepository::new(ctx.db.clone())
+        .set_tenant_session_limit(&tenant, &limit, Uuid::new_v4())
+        .await
+        .expect("Failed to enable sliding sessions");
+}
+
+fn header<'a>(response: &'a Response, name: &str) -> Option<&'a str> {
+    response.headers().get(name).and_then(|value| value.to_str().ok())
+}
+
+#[tokio::test]
+async fn test_expiry_headers_follow_the_remaining_lifetime() {
+    init_test_logging();
+    let ctx = TestContext::new().await;
+    let app = test_router(&ctx);
+
+    for (remaining, advised) in [(900, false), (600, false), (181, false), (180, true), (30, true)] {
+        let (token, _) = token(&ctx, remaining, None);
+        let response = call(&app, &token, "https").await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let expires_in: i64 = header(&response, TOKEN_EXPIRES_IN_HEADER).unwrap().parse().unwrap();
+        assert!((remaining - 2..=remaining).contains(&expires_in), "{} left, header says {}", remaining, expires_in);
+        assert_eq!(header(&response, TOKEN_REFRESH_ADVISED_HEADER) == Some("true"), advised, "{} left", remaining);
+        // Sliding sessions are off unless the tenant turns them on
+        assert!(header(&response, REFRESHED_ACCESS_TOKEN_HEADER).is_none());
+    }
+
+    ctx.cleanup().await;
+}
+
+#[tokio::test]
+async fn test_sliding_session_rotates_the_token_once_with_a_grace_period() {
+    init_test_logging();
+    let ctx = TestContext::new().await;
+    enable_sliding_sessions(&ctx).await;
+    let app = test_router(&ctx);
+
+    // Outside the advisory window nothing is rotated
+    let (fresh, _) = token(&ctx, 600, None);
+    assert!(header(&call(&app, &fresh, "https").await, REFRESHED_ACCESS_TOKEN_HEADER).is_none());
+
+    // Never over plain HTTP
+    let (expiring, jti) = token(&ctx, 60, None);
+    let response = call(&app, &expiring, "http").await;
+    assert_eq!(header(&response, TOKEN_REFRESH_ADVISED_HEADER), Some("true"));
+    assert!(header(&response, REFRESHED_ACCESS_TOKEN_HEADER).is_none());
+
+    let response = call(&app, &expiring, "https").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let refreshed = header(&response, REFRESHED_ACCESS_TOKEN_HEADER).expect("a fresh token").to_string();
+    let claims = ctx.auth_service.jwt_service().verify_access_token(&refreshed).unwrap();
+    assert_ne!(claims.jti, jti);
+    assert_eq!(claims.exp - claims.iat, 900);
+
+    // The old token still passes during the grace period but is not rotated twice
+    let response = call(&app, &expiring, "https").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(header(&response, REFRESHED_ACCESS_TOKEN_HEADER).is_none());
+
+    // Once the grace period is over only the new token works
+    let mut conn = ctx.redis.clone();
+    let _: () = redis::AsyncCommands::set(&mut conn, format!("token_revoked_after:{}", jti), chrono::Utc::now().timestamp())
+        .await
+        .unwrap();
+    assert_eq!(call(&app, &expiring, "https").await.status(), StatusCode::UNAUTHORIZED);
+    let response = call(&app, &refreshed, "https").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(header(&response, TOKEN_REFRESH_ADVISED_HEADER).is_none());
+
+    ctx.cleanup().await;
+}
+
+#[tokio::test]
+async fn test_sliding_session_never_extends_impersonation() {
+    init_test_logging();
+    let ctx = TestContext::new().await;
+    enable_sliding_sessions(&ctx).await;
+    let app = test_router(&ctx);
+
+    let (impersonation, _) = token(&ctx, 60, Some(Uuid::new_v4()));
+    let response = call(&app, &impersonation, "https").await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(header(&response, TOKEN_REFRESH_ADVISED_HEADER), Some("true"));
+    assert!(header(&response, REFRESHED_ACCESS_TOKEN_HEADER).is_none());
+
+    ctx.cleanup().await;
+}
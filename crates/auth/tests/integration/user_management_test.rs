@@ -341,6 +341,8 @@ async fn generate_test_jwt(ctx: &TestContext, user_id: Uuid, permissions: Vec<St
         iat: chrono::Utc::now().timestamp(),
         jti: Uuid::new_v4().to_string(),
         impersonator_id: None,
+        portal: None,
+        sid: None,
     };
     
     // Use a test JWT secret - in production this would come from config
@@ -354,6 +356,7 @@ async fn create_auth_state(ctx: &TestContext) -> AuthState {
         jwt_service: ctx.auth_service.jwt_service().clone(),
         db: Arc::new(ctx.db.clone()),
         redis: ctx.redis.clone(),
+        tenants: ctx.auth_service.tenant_resolver(),
     }
 }
 
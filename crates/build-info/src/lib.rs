@@ -0,0 +1,123 @@
+//! Build metadata shared by the ERP binaries
+//!
+//! A binary's build script calls [`emit`], which embeds the git commit, build
+//! time and compiler version as compile-time environment variables, and the
+//! binary reads them back with [`build_info!`]. The API server publishes the
+//! result in `/health`; `erp-deploy status` reads it from there and compares
+//! it with its own.
+//!
+//! Builds without a git checkout (e.g. inside a Docker context that skips
+//! `.git`) can pass `ERP_BUILD_GIT_COMMIT` / `ERP_BUILD_GIT_DIRTY` instead.
+
+use serde::{Deserialize, Serialize};
+use std::{env, path::Path, process::Command};
+
+/// Build metadata as published by the API server under `build` in `/health`;
+/// every field is safe to publish
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuildInfo {
+    pub version: String,
+    pub commit: String,
+    pub commit_short: String,
+    /// Built from a checkout with uncommitted changes
+    pub dirty: bool,
+    pub built_at: String,
+    pub rustc_version: String,
+}
+
+impl BuildInfo {
+    /// `version+commit_short`, with a `-dirty` suffix for uncommitted builds
+    pub fn label(&self) -> String {
+        let dirty = if self.dirty { "-dirty" } else { "" };
+        format!("{}+{}{}", self.version, self.commit_short, dirty)
+    }
+}
+
+/// Metadata of the crate this expands in, embedded by its build script through [`emit`]
+#[macro_export]
+macro_rules! build_info {
+    () => {
+        $crate::BuildInfo {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            commit: env!("ERP_BUILD_GIT_COMMIT").to_string(),
+            commit_short: env!("ERP_BUILD_GIT_COMMIT_SHORT").to_string(),
+            dirty: env!("ERP_BUILD_GIT_DIRTY") == "true",
+            built_at: env!("ERP_BUILD_TIMESTAMP").to_string(),
+            rustc_version: env!("ERP_BUILD_RUSTC_VERSION").to_string(),
+        }
+    };
+}
+
+/// Embed the build metadata; call from a build script's `main`
+pub fn emit() {
+    let commit = env::var("ERP_BUILD_GIT_COMMIT")
+        .ok()
+        .or_else(|| git(&["rev-parse", "HEAD"]))
+        .unwrap_or_else(|| "unknown".to_string());
+    let dirty = env::var("ERP_BUILD_GIT_DIRTY")
+        .ok()
+        .or_else(|| git(&["status", "--porcelain", "--untracked-files=no"]).map(|s| (!s.is_empty()).to_string()))
+        .unwrap_or_else(|| "false".to_string());
+
+    // SOURCE_DATE_EPOCH keeps reproducible builds reproducible
+    let built_at = env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.parse::<i64>().ok())
+        .and_then(|epoch| chrono::DateTime::from_timestamp(epoch, 0))
+        .unwrap_or_else(chrono::Utc::now)
+        .to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let rustc_version = Command::new(rustc)
+        .arg("--version")
+        .output()
+        .ok()
+        .and_then(|out| String::from_utf8(out.stdout).ok())
+        .map(|s| s.trim().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+
+    let commit_short: String = commit.chars().take(7).collect();
+
+    println!("cargo:rustc-env=ERP_BUILD_GIT_COMMIT={}", commit);
+    println!("cargo:rustc-env=ERP_BUILD_GIT_COMMIT_SHORT={}", commit_short);
+    println!("cargo:rustc-env=ERP_BUILD_GIT_DIRTY={}", dirty);
+    println!("cargo:rustc-env=ERP_BUILD_TIMESTAMP={}", built_at);
+    println!("cargo:rustc-env=ERP_BUILD_RUSTC_VERSION={}", rustc_version);
+
+    println!("cargo:rerun-if-env-changed=ERP_BUILD_GIT_COMMIT");
+    println!("cargo:rerun-if-env-changed=ERP_BUILD_GIT_DIRTY");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+    if let Some(git_dir) = git(&["rev-parse", "--git-dir"]) {
+        let git_dir = Path::new(&git_dir);
+        println!("cargo:rerun-if-changed={}", git_dir.join("HEAD").display());
+        println!("cargo:rerun-if-changed={}", git_dir.join("index").display());
+    }
+}
+
+fn git(args: &[&str]) -> Option<String> {
+    let out = Command::new("git").args(args).output().ok()?;
+    if !out.status.success() {
+        return None;
+    }
+    String::from_utf8(out.stdout).ok().map(|s| s.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_label_marks_dirty_builds() {
+        let mut build = BuildInfo {
+            version: "0.1.0".to_string(),
+            commit: "abc1234def".to_string(),
+            commit_short: "abc1234".to_string(),
+            dirty: false,
+            built_at: "2024-01-01T00:00:00Z".to_string(),
+            rustc_version: "rustc 1.80.0".to_string(),
+        };
+        assert_eq!(build.label(), "0.1.0+abc1234");
+        build.dirty = true;
+        assert_eq!(build.label(), "0.1.0+abc1234-dirty");
+    }
+}
@@ -15,7 +15,8 @@ CREATE TABLE {{schema}}.users (
     two_factor_enabled_at TIMESTAMPTZ,
     last_login_at TIMESTAMPTZ,
     created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
-    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    deleted_at TIMESTAMPTZ
 );
 
 -- Roles table
@@ -71,6 +72,12 @@ CREATE INDEX idx_{{schema}}_users_is_active ON {{schema}}.users(is_active);
 CREATE INDEX idx_{{schema}}_audit_log_timestamp ON {{schema}}.audit_log(timestamp);
 CREATE INDEX idx_{{schema}}_audit_log_user_id ON {{schema}}.audit_log(user_id);
 CREATE INDEX idx_{{schema}}_audit_log_event_type ON {{schema}}.audit_log(event_type);
+-- Role editor search and reverse lookups (pg_trgm comes with migration 036)
+CREATE INDEX idx_{{schema}}_roles_search ON {{schema}}.roles
+    USING GIN (lower(name || ' ' || coalesce(description, '')) public.gin_trgm_ops);
+CREATE INDEX idx_{{schema}}_permissions_search ON {{schema}}.permissions
+    USING GIN (lower(resource || ':' || action || ' ' || coalesce(description, '')) public.gin_trgm_ops);
+CREATE INDEX idx_{{schema}}_role_permissions_permission_id ON {{schema}}.role_permissions(permission_id);
 
 -- Triggers for updated_at
 CREATE TRIGGER update_{{schema}}_users_updated_at 
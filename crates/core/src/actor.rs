@@ -0,0 +1,187 @@
+//! # Actors
+//!
+//! Every write records who made it in its `created_by`, `updated_by` or
+//! similar column. The actor of a request is the authenticated user the auth
+//! middleware puts into the request as an [`ActorContext`]; background jobs
+//! act as [`ActorContext::system`], whose well-known id [`SYSTEM_ACTOR_ID`]
+//! marks their changes.
+//!
+//! The nil UUID is never an actor: repositories check the actor of each
+//! write with [`ensure_actor`] and refuse it, so a write whose actor got
+//! lost on the way fails instead of being recorded as made by nobody. Rows
+//! written before that check can be found with [`nil_actor_report`].
+
+#[cfg(feature = "axum")]
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{request::Parts, StatusCode},
+    response::Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Row};
+use uuid::Uuid;
+
+use crate::error::{Error, ErrorCode, Result};
+
+/// Actor of background jobs and other writes no user asked for
+pub const SYSTEM_ACTOR_ID: Uuid = Uuid::from_u128(0x53595354_454d_4000_8000_000000000000);
+
+/// Columns holding the actor of a write
+const ACTOR_COLUMN_PATTERN: &str = "(_by|operator_id)$";
+
+/// Who a request or job acts as; nobody by default
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ActorContext {
+    /// The signed-in user; while impersonating, the user being impersonated
+    pub user_id: Option<Uuid>,
+    /// The non-human account acting, e.g. [`SYSTEM_ACTOR_ID`] for jobs
+    pub service_account_id: Option<Uuid>,
+    /// The admin behind an impersonation
+    pub impersonated_by: Option<Uuid>,
+}
+
+impl ActorContext {
+    pub fn user(user_id: Uuid) -> Self {
+        Self {
+            user_id: Some(user_id),
+            service_account_id: None,
+            impersonated_by: None,
+        }
+    }
+
+    pub fn system() -> Self {
+        Self {
+            user_id: None,
+            service_account_id: Some(SYSTEM_ACTOR_ID),
+            impersonated_by: None,
+        }
+    }
+
+    pub fn with_impersonator(mut self, impersonated_by: Option<Uuid>) -> Self {
+        self.impersonated_by = impersonated_by;
+        self
+    }
+
+    /// The id recorded for writes of this actor; fails if there is none
+    pub fn id(&self) -> Result<Uuid> {
+        ensure_actor(self.user_id.or(self.service_account_id).unwrap_or_default())
+    }
+
+    pub fn is_system(&self) -> bool {
+        self.user_id.is_none() && self.service_account_id == Some(SYSTEM_ACTOR_ID)
+    }
+}
+
+/// Refuse a write recorded as made by the nil UUID
+pub fn ensure_actor(actor_id: Uuid) -> Result<Uuid> {
+    if actor_id.is_nil() {
+        return Err(Error::new(
+            ErrorCode::AuthenticationRequired,
+            "Writes need an identified actor; background jobs act as the system actor",
+        ));
+    }
+    Ok(actor_id)
+}
+
+/// Rows of one actor column recorded as made by the nil UUID
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct NilActorCount {
+    pub schema: String,
+    pub table: String,
+    pub column: String,
+    pub rows: i64,
+}
+
+/// Rows with a nil actor per table and actor column, in the public schema
+/// and every tenant schema; columns without any are left out
+pub async fn nil_actor_report(pool: &PgPool) -> Result<Vec<NilActorCount>> {
+    let columns = sqlx::query(
+        r#"
+        SELECT table_schema, table_name, column_name
+        FROM information_schema.columns
+        JOIN information_schema.tables USING (table_schema, table_name)
+        WHERE table_schema NOT IN ('pg_catalog', 'information_schema')
+          AND table_type = 'BASE TABLE'
+          AND data_type = 'uuid' AND column_name ~ $1
+        ORDER BY table_schema, table_name, column_name
+        "#,
+    )
+    .bind(ACTOR_COLUMN_PATTERN)
+    .fetch_all(pool)
+    .await?;
+
+    let mut report = Vec::new();
+    for row in columns {
+        let schema: String = row.get("table_schema");
+        let table: String = row.get("table_name");
+        let column: String = row.get("column_name");
+        // Names come from the catalog; quoted all the same
+        let rows: i64 = sqlx::query_scalar(&format!(
+            "SELECT COUNT(*) FROM \"{}\".\"{}\" WHERE \"{}\" = '00000000-0000-0000-0000-000000000000'",
+            schema.replace('"', "\"\""),
+            table.replace('"', "\"\""),
+            column.replace('"', "\"\""),
+        ))
+        .fetch_one(pool)
+        .await?;
+        if rows > 0 {
+            report.push(NilActorCount { schema, table, column, rows });
+        }
+    }
+    Ok(report)
+}
+
+// Axum FromRequestParts implementation for ActorContext
+#[cfg(feature = "axum")]
+#[async_trait]
+impl<S> FromRequestParts<S> for ActorContext
+where
+    S: Send + Sync,
+{
+    type Rejection = (StatusCode, Json<serde_json::Value>);
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> std::result::Result<Self, Self::Rejection> {
+        parts.extensions.get::<ActorContext>().copied().ok_or_else(|| {
+            (
+                StatusCode::UNAUTHORIZED,
+                Json(serde_json::json!({
+                    "error": "Authentication required"
+                })),
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nil_actor_is_refused() {
+        assert!(ensure_actor(Uuid::nil()).is_err());
+        let user = Uuid::new_v4();
+        assert_eq!(ensure_actor(user).unwrap(), user);
+    }
+
+    #[test]
+    fn test_actor_id_prefers_the_user() {
+        let user = Uuid::new_v4();
+        let admin = Uuid::new_v4();
+        let actor = ActorContext::user(user).with_impersonator(Some(admin));
+        assert_eq!(actor.id().unwrap(), user);
+        assert!(!actor.is_system());
+
+        let system = ActorContext::system();
+        assert_eq!(system.id().unwrap(), SYSTEM_ACTOR_ID);
+        assert!(system.is_system());
+
+        assert!(ActorContext::default().id().is_err());
+    }
+
+    #[test]
+    fn test_system_actor_is_not_nil() {
+        assert!(!SYSTEM_ACTOR_ID.is_nil());
+        assert_eq!(SYSTEM_ACTOR_ID.to_string(), "53595354-454d-4000-8000-000000000000");
+    }
+}
@@ -2,8 +2,10 @@ pub mod event;
 pub mod logger;
 pub mod repository;
 pub mod traits;
+pub mod writer;
 
 pub use event::{AuditEvent, AuditEventBuilder, EventSeverity, EventType, EventOutcome};
 pub use logger::AuditLogger;
 pub use repository::{AuditRepository, DatabaseAuditRepository};
-pub use traits::{AuditBackend, Auditable};
\ No newline at end of file
+pub use traits::{AuditBackend, AuditFilter, AuditFilterBuilder, Auditable, PaginationOptions, PaginationResult, SortOrder};
+pub use writer::{AuditSpill, BufferedAuditWriter, OverflowStrategy, RedisAuditSpill};
@@ -1,20 +1,31 @@
-use super::AuditEvent;
+use super::{AuditEvent, EventOutcome, EventSeverity, EventType};
 use crate::error::Result;
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
+use serde::Serialize;
 use serde_json::Value;
+use uuid::Uuid;
 
 /// Trait for audit backends (database, file, remote, etc.)
 #[async_trait]
 pub trait AuditBackend: Send + Sync {
     /// Store an audit event
     async fn store_event(&self, event: &AuditEvent) -> Result<()>;
+
+    /// Store a batch of audit events; backends that can should write them at once
+    async fn store_events(&self, events: &[AuditEvent]) -> Result<()> {
+        for event in events {
+            self.store_event(event).await?;
+        }
+        Ok(())
+    }
     
-    /// Retrieve audit events with filtering
-    async fn retrieve_events(
+    /// One page of the audit events matching the filter, with their total
+    async fn query(
         &self,
         filter: &AuditFilter,
-    ) -> Result<Vec<AuditEvent>>;
+        pagination: PaginationOptions,
+    ) -> Result<PaginationResult<AuditEvent>>;
     
     /// Count audit events matching filter
     async fn count_events(&self, filter: &AuditFilter) -> Result<u64>;
@@ -35,39 +46,79 @@ pub struct BackendHealth {
     pub events_stored_today: Option<u64>,
 }
 
-/// Filter for querying audit events
+/// Filter for querying audit events; empty lists and `None` match everything
 #[derive(Debug, Clone, Default)]
 pub struct AuditFilter {
-    /// Start time range
-    pub start_time: Option<DateTime<Utc>>,
-    /// End time range
-    pub end_time: Option<DateTime<Utc>>,
-    /// Filter by actor ID
-    pub actor_id: Option<String>,
-    /// Filter by tenant ID
-    pub tenant_id: Option<String>,
-    /// Filter by event types
-    pub event_types: Option<Vec<String>>,
-    /// Filter by severity
-    pub severities: Option<Vec<String>>,
-    /// Filter by resource type
-    pub resource_type: Option<String>,
-    /// Filter by resource ID
+    /// Any of these event types
+    pub event_types: Vec<EventType>,
+    /// Any of these severities
+    pub severities: Vec<EventSeverity>,
+    /// Any of these outcomes
+    pub outcomes: Vec<EventOutcome>,
+    /// Type of the resource acted upon, e.g. `customer`
+    pub resource_kind: Option<String>,
     pub resource_id: Option<String>,
-    /// Filter by outcome
-    pub outcomes: Option<Vec<String>>,
-    /// Filter by tags
-    pub tags: Option<Vec<String>>,
-    /// Search in description
-    pub description_contains: Option<String>,
-    /// Pagination limit
-    pub limit: Option<u32>,
-    /// Pagination offset
-    pub offset: Option<u32>,
+    pub actor_id: Option<Uuid>,
+    pub tenant_id: Option<Uuid>,
+    /// Events at or after this time
+    pub from: Option<DateTime<Utc>>,
+    /// Events at or before this time
+    pub to: Option<DateTime<Utc>>,
+    /// Case-insensitive substring of the description
+    pub text_search: Option<String>,
     /// Sort order
     pub sort_order: SortOrder,
 }
 
+/// Page of a query, counted from 1
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PaginationOptions {
+    pub page: u32,
+    pub per_page: u32,
+}
+
+impl PaginationOptions {
+    /// Most events returned per page
+    pub const MAX_PER_PAGE: u32 = 500;
+
+    /// Out of range values are clamped to the first page and 1..=[`Self::MAX_PER_PAGE`]
+    pub fn new(page: u32, per_page: u32) -> Self {
+        Self { page: page.max(1), per_page: per_page.clamp(1, Self::MAX_PER_PAGE) }
+    }
+
+    pub fn offset(&self) -> u64 {
+        u64::from(self.page.saturating_sub(1)) * u64::from(self.per_page)
+    }
+}
+
+impl Default for PaginationOptions {
+    fn default() -> Self {
+        Self::new(1, 50)
+    }
+}
+
+/// One page of query results with the total across all pages
+#[derive(Debug, Clone, Serialize)]
+pub struct PaginationResult<T> {
+    pub items: Vec<T>,
+    pub total: u64,
+    pub page: u32,
+    pub per_page: u32,
+    pub total_pages: u32,
+}
+
+impl<T> PaginationResult<T> {
+    pub fn new(items: Vec<T>, total: u64, pagination: PaginationOptions) -> Self {
+        Self {
+            items,
+            total,
+            page: pagination.page,
+            per_page: pagination.per_page,
+            total_pages: total.div_ceil(u64::from(pagination.per_page)) as u32,
+        }
+    }
+}
+
 /// Sort order for audit events
 #[derive(Debug, Clone)]
 pub enum SortOrder {
@@ -106,64 +157,45 @@ impl AuditFilterBuilder {
         }
     }
 
-    pub fn time_range(mut self, start: DateTime<Utc>, end: DateTime<Utc>) -> Self {
-        self.filter.start_time = Some(start);
-        self.filter.end_time = Some(end);
-        self
-    }
-
-    pub fn actor_id(mut self, actor_id: impl Into<String>) -> Self {
-        self.filter.actor_id = Some(actor_id.into());
-        self
-    }
-
-    pub fn tenant_id(mut self, tenant_id: impl Into<String>) -> Self {
-        self.filter.tenant_id = Some(tenant_id.into());
-        self
-    }
-
-    pub fn event_types(mut self, event_types: Vec<String>) -> Self {
-        self.filter.event_types = Some(event_types);
-        self
-    }
-
-    pub fn severities(mut self, severities: Vec<String>) -> Self {
-        self.filter.severities = Some(severities);
+    pub fn time_range(mut self, from: DateTime<Utc>, to: DateTime<Utc>) -> Self {
+        self.filter.from = Some(from);
+        self.filter.to = Some(to);
         self
     }
 
-    pub fn resource_type(mut self, resource_type: impl Into<String>) -> Self {
-        self.filter.resource_type = Some(resource_type.into());
+    pub fn actor_id(mut self, actor_id: Uuid) -> Self {
+        self.filter.actor_id = Some(actor_id);
         self
     }
 
-    pub fn resource_id(mut self, resource_id: impl Into<String>) -> Self {
-        self.filter.resource_id = Some(resource_id.into());
+    pub fn tenant_id(mut self, tenant_id: Uuid) -> Self {
+        self.filter.tenant_id = Some(tenant_id);
         self
     }
 
-    pub fn outcomes(mut self, outcomes: Vec<String>) -> Self {
-        self.filter.outcomes = Some(outcomes);
+    pub fn event_types(mut self, event_types: Vec<EventType>) -> Self {
+        self.filter.event_types = event_types;
         self
     }
 
-    pub fn tags(mut self, tags: Vec<String>) -> Self {
-        self.filter.tags = Some(tags);
+    pub fn severities(mut self, severities: Vec<EventSeverity>) -> Self {
+        self.filter.severities = severities;
         self
     }
 
-    pub fn description_contains(mut self, text: impl Into<String>) -> Self {
-        self.filter.description_contains = Some(text.into());
+    pub fn outcomes(mut self, outcomes: Vec<EventOutcome>) -> Self {
+        self.filter.outcomes = outcomes;
         self
     }
 
-    pub fn limit(mut self, limit: u32) -> Self {
-        self.filter.limit = Some(limit);
+    pub fn resource(mut self, kind: impl Into<String>, id: Option<String>) -> Self {
+        self.filter.resource_kind = Some(kind.into());
+        self.filter.resource_id = id;
         self
     }
 
-    pub fn offset(mut self, offset: u32) -> Self {
-        self.filter.offset = Some(offset);
+    pub fn text_search(mut self, text: impl Into<String>) -> Self {
+        self.filter.text_search = Some(text.into());
         self
     }
 
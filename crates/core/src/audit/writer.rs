@@ -0,0 +1,561 @@
+//! # Buffered Audit Writer
+//!
+//! Writing an audit event inline puts a database insert on hot paths like
+//! login, and stalls requests while the audit table is locked, for example by
+//! the archival job. [`BufferedAuditWriter`] is an [`AuditBackend`] that
+//! queues events in a bounded buffer instead; a background task writes them to
+//! the wrapped backend in batches of up to `batch_size`, at least every
+//! `flush_interval_ms`. Callers keep using
+//! [`AuditLogger::log_event`](super::AuditLogger::log_event) unchanged.
+//!
+//! When the buffer is full, the event's severity decides
+//! ([`AuditWriterConfig`]): `block` waits up to `block_timeout_ms` for room,
+//! then spills the event; `drop` discards it and counts it. Batches the
+//! backend refuses are spilled as well. The spill is a Redis list
+//! ([`RedisAuditSpill`]) that survives a crash of the instance; it is
+//! replayed into the backend when the writer starts. Without a spill, events
+//! that could not be buffered are reported to the caller as an error, and
+//! failed batches are lost and counted.
+//!
+//! [`BufferedAuditWriter::flush`] writes everything buffered so far; the
+//! server calls it on shutdown so a deploy loses no events.
+//!
+//! Reads (`retrieve_events`, `count_events`) go to the wrapped backend and do
+//! not see events that are still buffered.
+
+use super::traits::{AuditBackend, AuditFilter, BackendHealth, PaginationOptions, PaginationResult};
+use super::event::{AuditEvent, EventSeverity};
+use crate::config::AuditWriterConfig;
+use crate::error::{Error, ErrorCode, Result};
+use crate::metrics::AuditMetrics;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use redis::aio::ConnectionManager;
+use redis::AsyncCommands;
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+use tracing::{error, info, warn};
+
+/// What happens to an event when the buffer is full
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverflowStrategy {
+    /// Wait for room, up to the block timeout, then spill
+    Block,
+    /// Discard the event and count it
+    Drop,
+}
+
+impl OverflowStrategy {
+    /// `block` or `drop`; anything else blocks, so no event is lost to a typo
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "drop" => OverflowStrategy::Drop,
+            _ => OverflowStrategy::Block,
+        }
+    }
+
+    pub fn for_severity(config: &AuditWriterConfig, severity: EventSeverity) -> Self {
+        match severity {
+            EventSeverity::Info => Self::parse(&config.info_overflow),
+            EventSeverity::Warning => Self::parse(&config.warning_overflow),
+            EventSeverity::Critical => Self::parse(&config.critical_overflow),
+        }
+    }
+}
+
+/// Severity as the `severity` label of the audit metrics
+fn severity_label(severity: EventSeverity) -> &'static str {
+    match severity {
+        EventSeverity::Info => "info",
+        EventSeverity::Warning => "warning",
+        EventSeverity::Critical => "critical",
+    }
+}
+
+/// Durable overflow for events that could not be buffered or written
+#[async_trait]
+pub trait AuditSpill: Send + Sync {
+    async fn push(&self, events: &[AuditEvent]) -> Result<()>;
+    /// Remove and return up to `max` of the oldest spilled events
+    async fn take(&self, max: usize) -> Result<Vec<AuditEvent>>;
+}
+
+/// Spilled events as JSON in a Redis list, oldest first
+pub struct RedisAuditSpill {
+    redis: ConnectionManager,
+    key: String,
+}
+
+impl RedisAuditSpill {
+    pub fn new(redis: ConnectionManager, key: impl Into<String>) -> Self {
+        Self { redis, key: key.into() }
+    }
+}
+
+#[async_trait]
+impl AuditSpill for RedisAuditSpill {
+    async fn push(&self, events: &[AuditEvent]) -> Result<()> {
+        let payloads = events.iter().map(serde_json::to_string).collect::<std::result::Result<Vec<_>, _>>()?;
+        let mut conn = self.redis.clone();
+        conn.rpush::<_, _, ()>(&self.key, payloads).await?;
+        Ok(())
+    }
+
+    async fn take(&self, max: usize) -> Result<Vec<AuditEvent>> {
+        let Some(count) = NonZeroUsize::new(max) else {
+            return Ok(Vec::new());
+        };
+        let mut conn = self.redis.clone();
+        let payloads: Vec<String> = conn.lpop(&self.key, Some(count)).await?;
+        payloads
+            .iter()
+            .map(|payload| serde_json::from_str(payload).map_err(Error::from))
+            .collect()
+    }
+}
+
+enum Command {
+    Event(Box<AuditEvent>),
+    Flush(oneshot::Sender<()>),
+}
+
+impl Command {
+    fn into_event(self) -> Option<Box<AuditEvent>> {
+        match self {
+            Command::Event(event) => Some(event),
+            Command::Flush(_) => None,
+        }
+    }
+}
+
+/// Audit backend that buffers events and writes them in batches
+pub struct BufferedAuditWriter {
+    backend: Arc<dyn AuditBackend>,
+    config: AuditWriterConfig,
+    spill: Option<Arc<dyn AuditSpill>>,
+    metrics: Option<AuditMetrics>,
+    sender: mpsc::Sender<Command>,
+    receiver: Mutex<Option<mpsc::Receiver<Command>>>,
+}
+
+impl BufferedAuditWriter {
+    pub fn new(backend: Arc<dyn AuditBackend>, config: AuditWriterConfig) -> Self {
+        let (sender, receiver) = mpsc::channel(config.buffer_size.max(1));
+        Self {
+            backend,
+            config,
+            spill: None,
+            metrics: None,
+            sender,
+            receiver: Mutex::new(Some(receiver)),
+        }
+    }
+
+    /// Spill events that cannot be buffered or written instead of losing them
+    pub fn with_spill(mut self, spill: Arc<dyn AuditSpill>) -> Self {
+        self.spill = Some(spill);
+        self
+    }
+
+    pub fn with_metrics(mut self, metrics: AuditMetrics) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    pub fn metrics(&self) -> Option<&AuditMetrics> {
+        self.metrics.as_ref()
+    }
+
+    /// Events buffered and not yet written
+    pub fn queue_depth(&self) -> usize {
+        self.sender.max_capacity() - self.sender.capacity()
+    }
+
+    /// Replay the spilled events, then write the buffer in the background;
+    /// only the first call starts the task
+    pub fn spawn(self: &Arc<Self>) -> Option<tokio::task::JoinHandle<()>> {
+        let receiver = self.receiver.lock().ok()?.take()?;
+        let batches = Batches {
+            backend: self.backend.clone(),
+            spill: self.spill.clone(),
+            metrics: self.metrics.clone(),
+        };
+        let batch_size = self.config.batch_size.max(1);
+        let flush_interval = Duration::from_millis(self.config.flush_interval_ms.max(1));
+
+        Some(tokio::spawn(async move {
+            match batches.replay(batch_size).await {
+                Ok(0) => {}
+                Ok(replayed) => info!("Replayed {} spilled audit events", replayed),
+                Err(e) => warn!("Replaying spilled audit events failed: {}", e),
+            }
+            batches.run(receiver, batch_size, flush_interval).await;
+        }))
+    }
+
+    /// Write every event buffered so far; fails if that takes longer than `timeout`
+    pub async fn flush(&self, timeout: Duration) -> Result<()> {
+        let (ack, done) = oneshot::channel();
+        let flushed = async {
+            self.sender
+                .send(Command::Flush(ack))
+                .await
+                .map_err(|_| Error::new(ErrorCode::InternalServerError, "Audit writer is not running"))?;
+            done.await
+                .map_err(|_| Error::new(ErrorCode::InternalServerError, "Audit writer stopped before the flush"))
+        };
+        tokio::time::timeout(timeout, flushed).await.map_err(|_| {
+            Error::new(
+                ErrorCode::Timeout,
+                format!("Audit buffer not written within {:?}, {} events left", timeout, self.queue_depth()),
+            )
+        })?
+    }
+
+    fn dropped(&self, event: &AuditEvent, reason: &str) {
+        if let Some(metrics) = &self.metrics {
+            metrics.dropped_total.with_label_values(&[severity_label(event.severity), reason]).inc();
+        }
+    }
+
+    /// Keep an event that found no room in the buffer
+    async fn overflow(&self, event: AuditEvent) -> Result<()> {
+        let Some(spill) = &self.spill else {
+            self.dropped(&event, "overflow");
+            return Err(Error::new(
+                ErrorCode::StorageError,
+                format!("Audit buffer full, event {} not stored", event.id),
+            ));
+        };
+        match spill.push(std::slice::from_ref(&event)).await {
+            Ok(()) => {
+                if let Some(metrics) = &self.metrics {
+                    metrics.spilled_total.inc();
+                }
+                Ok(())
+            }
+            Err(e) => {
+                self.dropped(&event, "overflow");
+                Err(e)
+            }
+        }
+    }
+
+    /// Room for an event the buffer had none for, by the event's overflow strategy
+    async fn full(&self, event: Box<AuditEvent>) -> Result<()> {
+        match OverflowStrategy::for_severity(&self.config, event.severity) {
+            OverflowStrategy::Drop => {
+                warn!(event_id = %event.id, event_type = %event.event_type, "Audit buffer full, event dropped");
+                self.dropped(&event, "overflow");
+                Ok(())
+            }
+            OverflowStrategy::Block => {
+                let timeout = Duration::from_millis(self.config.block_timeout_ms);
+                match self.sender.send_timeout(Command::Event(event), timeout).await {
+                    Ok(()) => Ok(()),
+                    Err(e) => match e.into_inner().into_event() {
+                        Some(event) => self.overflow(*event).await,
+                        None => Ok(()),
+                    },
+                }
+            }
+        }
+    }
+
+    async fn enqueue(&self, event: AuditEvent) -> Result<()> {
+        let rejected = match self.sender.try_send(Command::Event(Box::new(event))) {
+            Ok(()) => None,
+            Err(e) => e.into_inner().into_event(),
+        };
+        let result = match rejected {
+            Some(event) => self.full(event).await,
+            None => Ok(()),
+        };
+        if let Some(metrics) = &self.metrics {
+            metrics.queue_depth.set(self.queue_depth() as i64);
+        }
+        result
+    }
+}
+
+/// The writer task's side: writes batches, spills what the backend refuses
+struct Batches {
+    backend: Arc<dyn AuditBackend>,
+    spill: Option<Arc<dyn AuditSpill>>,
+    metrics: Option<AuditMetrics>,
+}
+
+impl Batches {
+    async fn replay(&self, batch_size: usize) -> Result<usize> {
+        let Some(spill) = &self.spill else {
+            return Ok(0);
+        };
+        let mut replayed = 0;
+        loop {
+            let events = spill.take(batch_size).await?;
+            if events.is_empty() {
+                return Ok(replayed);
+            }
+            if let Err(e) = self.backend.store_events(&events).await {
+                // Back on the list for the next start
+                spill.push(&events).await?;
+                return Err(e);
+            }
+            replayed += events.len();
+        }
+    }
+
+    async fn write(&self, batch: &mut Vec<AuditEvent>) {
+        if batch.is_empty() {
+            return;
+        }
+        if let Some(metrics) = &self.metrics {
+            metrics.batch_size.observe(batch.len() as f64);
+        }
+        if let Err(e) = self.backend.store_events(batch).await {
+            let spilled = match &self.spill {
+                Some(spill) => spill.push(batch).await.map_err(|spill_error| {
+                    error!("Spilling {} audit events failed: {}", batch.len(), spill_error);
+                }),
+                None => Err(()),
+            };
+            match spilled {
+                Ok(()) => {
+                    warn!("Writing {} audit events failed, spilled for replay: {}", batch.len(), e);
+                    if let Some(metrics) = &self.metrics {
+                        metrics.spilled_total.inc_by(batch.len() as u64);
+                    }
+                }
+                Err(()) => {
+                    error!("Writing {} audit events failed, events lost: {}", batch.len(), e);
+                    if let Some(metrics) = &self.metrics {
+                        for event in batch.iter() {
+                            metrics
+                                .dropped_total
+                                .with_label_values(&[severity_label(event.severity), "write_failed"])
+                                .inc();
+                        }
+                    }
+                }
+            }
+        }
+        batch.clear();
+    }
+
+    async fn run(&self, mut receiver: mpsc::Receiver<Command>, batch_size: usize, flush_interval: Duration) {
+        let mut batch = Vec::with_capacity(batch_size);
+        let mut ticker = tokio::time::interval(flush_interval);
+        loop {
+            tokio::select! {
+                command = receiver.recv() => match command {
+                    Some(Command::Event(event)) => {
+                        batch.push(*event);
+                        if batch.len() >= batch_size {
+                            self.write(&mut batch).await;
+                        }
+                    }
+                    Some(Command::Flush(ack)) => {
+                        self.write(&mut batch).await;
+                        let _ = ack.send(());
+                    }
+                    None => {
+                        self.write(&mut batch).await;
+                        return;
+                    }
+                },
+                _ = ticker.tick() => self.write(&mut batch).await,
+            }
+            if let Some(metrics) = &self.metrics {
+                metrics.queue_depth.set(receiver.len() as i64);
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl AuditBackend for BufferedAuditWriter {
+    async fn store_event(&self, event: &AuditEvent) -> Result<()> {
+        self.enqueue(event.clone()).await
+    }
+
+    async fn query(&self, filter: &AuditFilter, pagination: PaginationOptions) -> Result<PaginationResult<AuditEvent>> {
+        self.backend.query(filter, pagination).await
+    }
+
+    async fn count_events(&self, filter: &AuditFilter) -> Result<u64> {
+        self.backend.count_events(filter).await
+    }
+
+    async fn health_check(&self) -> Result<BackendHealth> {
+        self.backend.health_check().await
+    }
+
+    async fn cleanup_old_events(&self, older_than: DateTime<Utc>) -> Result<u64> {
+        self.backend.cleanup_old_events(older_than).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::event::EventType;
+
+    /// Records the batches it was given
+    #[derive(Default)]
+    struct MemoryBackend {
+        batches: Mutex<Vec<Vec<String>>>,
+    }
+
+    impl MemoryBackend {
+        fn batch_sizes(&self) -> Vec<usize> {
+            self.batches.lock().unwrap().iter().map(Vec::len).collect()
+        }
+
+        fn stored(&self) -> Vec<String> {
+            self.batches.lock().unwrap().concat()
+        }
+    }
+
+    #[async_trait]
+    impl AuditBackend for MemoryBackend {
+        async fn store_event(&self, event: &AuditEvent) -> Result<()> {
+            self.store_events(std::slice::from_ref(event)).await
+        }
+
+        async fn store_events(&self, events: &[AuditEvent]) -> Result<()> {
+            self.batches.lock().unwrap().push(events.iter().map(|event| event.id.clone()).collect());
+            Ok(())
+        }
+
+        async fn query(&self, _filter: &AuditFilter, pagination: PaginationOptions) -> Result<PaginationResult<AuditEvent>> {
+            Ok(PaginationResult::new(Vec::new(), 0, pagination))
+        }
+
+        async fn count_events(&self, _filter: &AuditFilter) -> Result<u64> {
+            Ok(self.stored().len() as u64)
+        }
+
+        async fn health_check(&self) -> Result<BackendHealth> {
+            Ok(BackendHealth { is_healthy: true, message: None, last_write: None, events_stored_today: None })
+        }
+
+        async fn cleanup_old_events(&self, _older_than: DateTime<Utc>) -> Result<u64> {
+            Ok(0)
+        }
+    }
+
+    #[derive(Default)]
+    struct MemorySpill {
+        events: Mutex<Vec<AuditEvent>>,
+    }
+
+    #[async_trait]
+    impl AuditSpill for MemorySpill {
+        async fn push(&self, events: &[AuditEvent]) -> Result<()> {
+            self.events.lock().unwrap().extend_from_slice(events);
+            Ok(())
+        }
+
+        async fn take(&self, max: usize) -> Result<Vec<AuditEvent>> {
+            let mut events = self.events.lock().unwrap();
+            let count = max.min(events.len());
+            Ok(events.drain(..count).collect())
+        }
+    }
+
+    fn config(buffer_size: usize, batch_size: usize) -> AuditWriterConfig {
+        AuditWriterConfig {
+            buffer_size,
+            batch_size,
+            // Only full batches and flushes write in these tests
+            flush_interval_ms: 3_600_000,
+            block_timeout_ms: 50,
+            ..Default::default()
+        }
+    }
+
+    fn event(severity: EventSeverity) -> AuditEvent {
+        AuditEvent::builder(EventType::AuthenticationAttempt, "Login").severity(severity).build()
+    }
+
+    #[tokio::test]
+    async fn test_events_are_written_in_batches() {
+        let backend = Arc::new(MemoryBackend::default());
+        let writer = Arc::new(BufferedAuditWriter::new(backend.clone(), config(100, 3)));
+        writer.spawn();
+        assert!(writer.spawn().is_none());
+
+        for _ in 0..7 {
+            writer.store_event(&event(EventSeverity::Info)).await.unwrap();
+        }
+        writer.flush(Duration::from_secs(5)).await.unwrap();
+
+        assert_eq!(backend.batch_sizes(), vec![3, 3, 1]);
+        assert_eq!(writer.queue_depth(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_full_buffer_drops_info_and_spills_critical_events() {
+        let backend = Arc::new(MemoryBackend::default());
+        let spill = Arc::new(MemorySpill::default());
+        let metrics = AuditMetrics::new("test").unwrap();
+        // Not started yet: the buffer fills like behind a locked audit table
+        let writer = Arc::new(
+            BufferedAuditWriter::new(backend.clone(), config(2, 10))
+                .with_spill(spill.clone())
+                .with_metrics(metrics.clone()),
+        );
+        writer.store_event(&event(EventSeverity::Info)).await.unwrap();
+        writer.store_event(&event(EventSeverity::Info)).await.unwrap();
+
+        writer.store_event(&event(EventSeverity::Info)).await.unwrap();
+        assert_eq!(metrics.dropped_total.with_label_values(&["info", "overflow"]).get(), 1);
+        assert!(spill.events.lock().unwrap().is_empty());
+
+        let critical = event(EventSeverity::Critical);
+        let started = std::time::Instant::now();
+        writer.store_event(&critical).await.unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(50));
+        assert_eq!(spill.events.lock().unwrap().len(), 1);
+        assert_eq!(metrics.spilled_total.get(), 1);
+        assert_eq!(writer.queue_depth(), 2);
+
+        // Started, the writer replays the spill before the buffer
+        writer.spawn();
+        writer.flush(Duration::from_secs(5)).await.unwrap();
+        assert_eq!(backend.stored().len(), 3);
+        assert_eq!(backend.stored()[0], critical.id);
+        assert!(spill.events.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_full_buffer_without_spill_reports_blocked_events() {
+        let writer = BufferedAuditWriter::new(Arc::new(MemoryBackend::default()), config(1, 10));
+        writer.store_event(&event(EventSeverity::Critical)).await.unwrap();
+
+        let error = writer.store_event(&event(EventSeverity::Critical)).await.unwrap_err();
+        assert_eq!(error.code, ErrorCode::StorageError);
+        assert_eq!(OverflowStrategy::parse("dorp"), OverflowStrategy::Block);
+    }
+
+    #[tokio::test]
+    async fn test_flush_on_shutdown_writes_the_buffer() {
+        let backend = Arc::new(MemoryBackend::default());
+        let writer = Arc::new(BufferedAuditWriter::new(backend.clone(), config(100, 50)));
+        // A writer that never started cannot flush
+        assert_eq!(writer.flush(Duration::from_millis(20)).await.unwrap_err().code, ErrorCode::Timeout);
+
+        writer.spawn();
+        let events: Vec<AuditEvent> = (0..5).map(|_| event(EventSeverity::Warning)).collect();
+        for event in &events {
+            writer.store_event(event).await.unwrap();
+        }
+        assert!(backend.stored().is_empty());
+
+        writer.flush(Duration::from_secs(5)).await.unwrap();
+        let ids: Vec<String> = events.iter().map(|event| event.id.clone()).collect();
+        assert_eq!(backend.stored(), ids);
+    }
+}
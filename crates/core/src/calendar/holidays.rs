@@ -0,0 +1,178 @@
+//! Nationwide public holidays by country
+//!
+//! Only holidays observed in the whole country are listed; regional ones
+//! (German states, Italian patron saints' days and the like) are added by hand as manual
+//! calendar days.
+
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+
+use super::{CalendarDay, DaySource};
+use crate::error::{Error, Result};
+
+/// ISO 3166-1 alpha-2 codes with a holiday set
+pub const COUNTRIES: [&str; 6] = ["AT", "BE", "DE", "FR", "IT", "NL"];
+
+/// Western (Gregorian) Easter Sunday, anonymous Gregorian algorithm
+pub fn easter_sunday(year: i32) -> Option<NaiveDate> {
+    let a = year % 19;
+    let b = year / 100;
+    let c = year % 100;
+    let d = b / 4;
+    let e = b % 4;
+    let f = (b + 8) / 25;
+    let g = (b - f + 1) / 3;
+    let h = (19 * a + b - d - g + 15) % 30;
+    let i = c / 4;
+    let k = c % 4;
+    let l = (32 + 2 * e + 2 * i - h - k) % 7;
+    let m = (a + 11 * h + 22 * l) / 451;
+    let month = (h + l - 7 * m + 114) / 31;
+    let day = (h + l - 7 * m + 114) % 31 + 1;
+    NaiveDate::from_ymd_opt(year, month as u32, day as u32)
+}
+
+/// Public holidays of a country in a year, `None` for countries without a set
+pub fn public_holidays(country: &str, year: i32) -> Option<Vec<(NaiveDate, &'static str)>> {
+    let easter = easter_sunday(year)?;
+    let fixed = |month: u32, day: u32| NaiveDate::from_ymd_opt(year, month, day).expect("valid holiday date");
+    let after_easter = |days: i64| easter + Duration::days(days);
+
+    let new_year = (fixed(1, 1), "New Year's Day");
+    let easter_monday = (after_easter(1), "Easter Monday");
+    let labour_day = (fixed(5, 1), "Labour Day");
+    let ascension = (after_easter(39), "Ascension Day");
+    let whit_monday = (after_easter(50), "Whit Monday");
+    let assumption = (fixed(8, 15), "Assumption Day");
+    let all_saints = (fixed(11, 1), "All Saints' Day");
+    let christmas = (fixed(12, 25), "Christmas Day");
+
+    let mut holidays = match country.to_ascii_uppercase().as_str() {
+        "AT" => vec![
+            new_year,
+            (fixed(1, 6), "Epiphany"),
+            easter_monday,
+            labour_day,
+            ascension,
+            whit_monday,
+            (after_easter(60), "Corpus Christi"),
+            assumption,
+            (fixed(10, 26), "National Day"),
+            all_saints,
+            (fixed(12, 8), "Immaculate Conception"),
+            christmas,
+            (fixed(12, 26), "St. Stephen's Day"),
+        ],
+        "BE" => vec![
+            new_year,
+            easter_monday,
+            labour_day,
+            ascension,
+            whit_monday,
+            (fixed(7, 21), "National Day"),
+            assumption,
+            all_saints,
+            (fixed(11, 11), "Armistice Day"),
+            christmas,
+        ],
+        "DE" => vec![
+            new_year,
+            (after_easter(-2), "Good Friday"),
+            easter_monday,
+            labour_day,
+            ascension,
+            whit_monday,
+            (fixed(10, 3), "German Unity Day"),
+            christmas,
+            (fixed(12, 26), "Second Day of Christmas"),
+        ],
+        "FR" => vec![
+            new_year,
+            easter_monday,
+            labour_day,
+            (fixed(5, 8), "Victory in Europe Day"),
+            ascension,
+            whit_monday,
+            (fixed(7, 14), "Bastille Day"),
+            assumption,
+            all_saints,
+            (fixed(11, 11), "Armistice Day"),
+            christmas,
+        ],
+        "IT" => vec![
+            new_year,
+            (fixed(1, 6), "Epiphany"),
+            easter_monday,
+            (fixed(4, 25), "Liberation Day"),
+            labour_day,
+            (fixed(6, 2), "Republic Day"),
+            assumption,
+            all_saints,
+            (fixed(12, 8), "Immaculate Conception"),
+            christmas,
+            (fixed(12, 26), "St. Stephen's Day"),
+        ],
+        "NL" => {
+            // King's Day moves to the 26th when the 27th is a Sunday
+            let kings_day = fixed(4, 27);
+            let kings_day = if kings_day.weekday() == Weekday::Sun { fixed(4, 26) } else { kings_day };
+            vec![
+                new_year,
+                easter_monday,
+                (kings_day, "King's Day"),
+                ascension,
+                whit_monday,
+                christmas,
+                (fixed(12, 26), "Second Day of Christmas"),
+            ]
+        }
+        _ => return None,
+    };
+    holidays.sort_by_key(|(date, _)| *date);
+    Some(holidays)
+}
+
+/// Public holidays as imported calendar days
+pub fn calendar_days(country: &str, year: i32) -> Result<Vec<CalendarDay>> {
+    let country = country.to_ascii_uppercase();
+    let holidays = public_holidays(&country, year).ok_or_else(|| {
+        Error::validation(format!(
+            "No holiday set for country {}; available: {}",
+            country,
+            COUNTRIES.join(", ")
+        ))
+    })?;
+    Ok(holidays
+        .into_iter()
+        .map(|(date, label)| CalendarDay {
+            date,
+            label: label.to_string(),
+            working_day: false,
+            source: DaySource::Imported,
+            country: Some(country.clone()),
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_easter_sunday() {
+        assert_eq!(easter_sunday(2024), NaiveDate::from_ymd_opt(2024, 3, 31));
+        assert_eq!(easter_sunday(2025), NaiveDate::from_ymd_opt(2025, 4, 20));
+        assert_eq!(easter_sunday(2038), NaiveDate::from_ymd_opt(2038, 4, 25));
+    }
+
+    #[test]
+    fn test_german_holidays_2025() {
+        let days = calendar_days("de", 2025).unwrap();
+        let dates: Vec<String> = days.iter().map(|d| d.date.format("%m-%d").to_string()).collect();
+        assert_eq!(
+            dates,
+            ["01-01", "04-18", "04-21", "05-01", "05-29", "06-09", "10-03", "12-25", "12-26"]
+        );
+        assert!(days.iter().all(|d| d.country.as_deref() == Some("DE") && d.source == DaySource::Imported));
+        assert!(calendar_days("XX", 2025).is_err());
+    }
+}
@@ -0,0 +1,305 @@
+//! # Business Calendar
+//!
+//! A tenant's working days: the weekdays it works on, plus dated exceptions.
+//! An exception is either a holiday, imported from a country set (see
+//! [`holidays`]) or entered by hand, or a working day on a date that would be
+//! off otherwise. Manual entries override imported ones for the same date.
+//!
+//! Scheduled jobs consult the calendar through a [`CalendarPolicy`]:
+//!
+//! - `always`: runs happen on their scheduled date
+//! - `working_days_only`: a run scheduled on a non-working day moves to the
+//!   next working day
+//! - `skip_holidays`: a run scheduled on a non-working day is dropped
+//!
+//! A run is identified by its logical date, the date it actually happens on.
+//! Several scheduled dates can land on the same working day (a Saturday and a
+//! Sunday run shifted onto Monday's own run); they share one logical date, so a
+//! job that claims its runs by logical date fires once.
+
+pub mod holidays;
+
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Longest a run is shifted; without a working day in this window it is dropped
+pub const MAX_SHIFT_DAYS: i64 = 31;
+
+/// How a scheduled job treats non-working days
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CalendarPolicy {
+    #[default]
+    Always,
+    /// Shift runs to the next working day
+    WorkingDaysOnly,
+    /// Drop runs on non-working days
+    SkipHolidays,
+}
+
+impl CalendarPolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CalendarPolicy::Always => "always",
+            CalendarPolicy::WorkingDaysOnly => "working_days_only",
+            CalendarPolicy::SkipHolidays => "skip_holidays",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "always" => Some(CalendarPolicy::Always),
+            "working_days_only" => Some(CalendarPolicy::WorkingDaysOnly),
+            "skip_holidays" => Some(CalendarPolicy::SkipHolidays),
+            _ => None,
+        }
+    }
+}
+
+/// Where a dated exception came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DaySource {
+    /// A country's public holiday set
+    Imported,
+    Manual,
+}
+
+impl DaySource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DaySource::Imported => "imported",
+            DaySource::Manual => "manual",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "imported" => Some(DaySource::Imported),
+            "manual" => Some(DaySource::Manual),
+            _ => None,
+        }
+    }
+}
+
+/// A dated exception to the working weekdays
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CalendarDay {
+    pub date: NaiveDate,
+    pub label: String,
+    /// `false` for a holiday, `true` for a working day on an otherwise free date
+    pub working_day: bool,
+    pub source: DaySource,
+    /// Country of an imported holiday
+    pub country: Option<String>,
+}
+
+impl CalendarDay {
+    pub fn holiday(date: NaiveDate, label: impl Into<String>) -> Self {
+        Self {
+            date,
+            label: label.into(),
+            working_day: false,
+            source: DaySource::Manual,
+            country: None,
+        }
+    }
+}
+
+/// ISO weekday number, 1 (Monday) to 7 (Sunday)
+pub fn weekday_from_iso(number: u32) -> Option<Weekday> {
+    match number {
+        1..=7 => Some(Weekday::try_from((number - 1) as u8).expect("valid weekday")),
+        _ => None,
+    }
+}
+
+/// Monday to Friday
+pub fn standard_working_days() -> Vec<Weekday> {
+    vec![Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri]
+}
+
+/// Working weekdays and dated exceptions of one tenant
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BusinessCalendar {
+    /// Indexed by days from Monday
+    working_weekdays: [bool; 7],
+    days: BTreeMap<NaiveDate, CalendarDay>,
+}
+
+/// Every day is a working day
+impl Default for BusinessCalendar {
+    fn default() -> Self {
+        Self {
+            working_weekdays: [true; 7],
+            days: BTreeMap::new(),
+        }
+    }
+}
+
+impl BusinessCalendar {
+    /// A manual exception wins over an imported one for the same date
+    pub fn new(working_days: impl IntoIterator<Item = Weekday>, days: impl IntoIterator<Item = CalendarDay>) -> Self {
+        let mut working_weekdays = [false; 7];
+        for weekday in working_days {
+            working_weekdays[weekday.num_days_from_monday() as usize] = true;
+        }
+
+        let mut by_date: BTreeMap<NaiveDate, CalendarDay> = BTreeMap::new();
+        for day in days {
+            let keep_existing = by_date
+                .get(&day.date)
+                .is_some_and(|existing| existing.source == DaySource::Manual && day.source == DaySource::Imported);
+            if !keep_existing {
+                by_date.insert(day.date, day);
+            }
+        }
+
+        Self {
+            working_weekdays,
+            days: by_date,
+        }
+    }
+
+    /// Working weekdays as ISO numbers, 1 (Monday) to 7 (Sunday)
+    pub fn working_weekdays(&self) -> Vec<u32> {
+        (1..=7).filter(|n| self.working_weekdays[(*n - 1) as usize]).collect()
+    }
+
+    /// The exception for a date, if any
+    pub fn day(&self, date: NaiveDate) -> Option<&CalendarDay> {
+        self.days.get(&date)
+    }
+
+    pub fn is_working_day(&self, date: NaiveDate) -> bool {
+        match self.days.get(&date) {
+            Some(day) => day.working_day,
+            None => self.working_weekdays[date.weekday().num_days_from_monday() as usize],
+        }
+    }
+
+    /// The first working day on or after `date`, within [`MAX_SHIFT_DAYS`]
+    pub fn next_working_day(&self, date: NaiveDate) -> Option<NaiveDate> {
+        (0..=MAX_SHIFT_DAYS)
+            .map(|offset| date + Duration::days(offset))
+            .find(|candidate| self.is_working_day(*candidate))
+    }
+
+    /// The logical date of a run scheduled for `scheduled`, `None` when it is dropped
+    pub fn run_date(&self, policy: CalendarPolicy, scheduled: NaiveDate) -> Option<NaiveDate> {
+        match policy {
+            CalendarPolicy::Always => Some(scheduled),
+            CalendarPolicy::WorkingDaysOnly => self.next_working_day(scheduled),
+            CalendarPolicy::SkipHolidays => self.is_working_day(scheduled).then_some(scheduled),
+        }
+    }
+
+    /// `today` if a run scheduled on it or shifted onto it is due
+    ///
+    /// `is_scheduled` tells the job's schedule, for example every day or the
+    /// last day of each month. Claim the returned logical date before running,
+    /// so the run fires once however many scheduled dates land on it.
+    pub fn due_run(
+        &self,
+        policy: CalendarPolicy,
+        today: NaiveDate,
+        is_scheduled: impl Fn(NaiveDate) -> bool,
+    ) -> Option<NaiveDate> {
+        let lookback = match policy {
+            CalendarPolicy::WorkingDaysOnly => MAX_SHIFT_DAYS,
+            CalendarPolicy::Always | CalendarPolicy::SkipHolidays => 0,
+        };
+        (0..=lookback)
+            .map(|offset| today - Duration::days(offset))
+            .filter(|scheduled| is_scheduled(*scheduled))
+            .any(|scheduled| self.run_date(policy, scheduled) == Some(today))
+            .then_some(today)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn german(year: i32) -> BusinessCalendar {
+        BusinessCalendar::new(standard_working_days(), holidays::calendar_days("DE", year).unwrap())
+    }
+
+    #[test]
+    fn test_shift_moves_runs_and_skip_drops_them() {
+        let calendar = german(2025);
+        // Good Friday, followed by the weekend and Easter Monday
+        let good_friday = date(2025, 4, 18);
+        assert!(!calendar.is_working_day(good_friday));
+
+        assert_eq!(calendar.run_date(CalendarPolicy::WorkingDaysOnly, good_friday), Some(date(2025, 4, 22)));
+        assert_eq!(calendar.run_date(CalendarPolicy::SkipHolidays, good_friday), None);
+        assert_eq!(calendar.run_date(CalendarPolicy::Always, good_friday), Some(good_friday));
+
+        // A weekly Friday job
+        let fridays = |d: NaiveDate| d.weekday() == Weekday::Fri;
+        assert_eq!(calendar.due_run(CalendarPolicy::WorkingDaysOnly, date(2025, 4, 22), fridays), Some(date(2025, 4, 22)));
+        assert_eq!(calendar.due_run(CalendarPolicy::SkipHolidays, date(2025, 4, 22), fridays), None);
+        assert_eq!(calendar.due_run(CalendarPolicy::WorkingDaysOnly, date(2025, 4, 23), fridays), None);
+    }
+
+    #[test]
+    fn test_holiday_on_weekend_shifts_to_monday() {
+        // New Year's Day 2028 is a Saturday
+        let calendar = german(2028);
+        let new_year = date(2028, 1, 1);
+        assert_eq!(new_year.weekday(), Weekday::Sat);
+        assert_eq!(calendar.day(new_year).map(|d| d.label.as_str()), Some("New Year's Day"));
+
+        let first_of_month = |d: NaiveDate| d.day() == 1;
+        assert_eq!(calendar.run_date(CalendarPolicy::WorkingDaysOnly, new_year), Some(date(2028, 1, 3)));
+        assert_eq!(calendar.due_run(CalendarPolicy::WorkingDaysOnly, date(2028, 1, 3), first_of_month), Some(date(2028, 1, 3)));
+        assert_eq!(calendar.due_run(CalendarPolicy::SkipHolidays, date(2028, 1, 3), first_of_month), None);
+    }
+
+    #[test]
+    fn test_shifted_runs_fire_once_per_logical_date() {
+        // Christmas 2027 falls on Saturday and Sunday; a daily job polled twice a day
+        let calendar = german(2027);
+        let mut claimed = HashSet::new();
+        let mut fired = Vec::new();
+        for day in 20..=31 {
+            for _poll in 0..2 {
+                if let Some(logical) = calendar.due_run(CalendarPolicy::WorkingDaysOnly, date(2027, 12, day), |_| true) {
+                    if claimed.insert(logical) {
+                        fired.push(logical);
+                    }
+                }
+            }
+        }
+
+        let expected: Vec<NaiveDate> = [20, 21, 22, 23, 24, 27, 28, 29, 30, 31].iter().map(|d| date(2027, 12, *d)).collect();
+        assert_eq!(fired, expected);
+    }
+
+    #[test]
+    fn test_manual_days_override_imported_holidays() {
+        let mut days = holidays::calendar_days("DE", 2025).unwrap();
+        days.push(CalendarDay {
+            date: date(2025, 10, 3),
+            label: "Stocktaking".to_string(),
+            working_day: true,
+            source: DaySource::Manual,
+            country: None,
+        });
+        days.push(CalendarDay::holiday(date(2025, 12, 24), "Christmas Eve"));
+        let calendar = BusinessCalendar::new(standard_working_days(), days);
+
+        assert!(calendar.is_working_day(date(2025, 10, 3)));
+        assert!(!calendar.is_working_day(date(2025, 12, 24)));
+        assert_eq!(calendar.next_working_day(date(2025, 12, 24)), Some(date(2025, 12, 29)));
+        assert_eq!(calendar.working_weekdays(), vec![1, 2, 3, 4, 5]);
+        assert!(BusinessCalendar::default().is_working_day(date(2025, 12, 25)));
+    }
+}
@@ -56,6 +56,8 @@ pub enum ErrorCode {
     DatabaseError = 6007,
     ConflictError = 6008,
     BusinessRuleViolation = 6009,
+    /// The tenant is at a limit of its plan that is enforced
+    PlanLimitExceeded = 6010,
 
     // Rate Limiting & Throttling Errors (7000-7999)
     RateLimitExceeded = 7000,
@@ -77,6 +79,28 @@ pub enum ErrorCode {
 }
 
 impl ErrorCode {
+    /// Every error code, in declaration order; the error catalog lists them
+    pub const ALL: [ErrorCode; 52] = [
+        ErrorCode::InternalServerError, ErrorCode::ConfigurationError, ErrorCode::ServiceUnavailable,
+        ErrorCode::Timeout, ErrorCode::ResourceExhausted, ErrorCode::DatabaseConnectionError,
+        ErrorCode::DatabaseConstraintViolation, ErrorCode::DatabaseTransactionError,
+        ErrorCode::DatabaseQueryError, ErrorCode::DatabaseMigrationError, ErrorCode::NetworkError,
+        ErrorCode::NetworkTimeout, ErrorCode::NetworkConnectionRefused, ErrorCode::ExternalServiceError,
+        ErrorCode::SerializationError, ErrorCode::AuthenticationRequired, ErrorCode::AuthenticationFailed,
+        ErrorCode::InvalidCredentials, ErrorCode::TokenExpired, ErrorCode::TokenInvalid,
+        ErrorCode::AuthorizationFailed, ErrorCode::PermissionDenied, ErrorCode::SecurityPolicyViolation,
+        ErrorCode::ValidationFailed, ErrorCode::InvalidInput, ErrorCode::MissingRequiredField,
+        ErrorCode::InvalidFormat, ErrorCode::ValueOutOfRange, ErrorCode::DuplicateValue,
+        ErrorCode::ResourceNotFound, ErrorCode::ResourceAlreadyExists, ErrorCode::ResourceLocked,
+        ErrorCode::ResourceInUse, ErrorCode::ResourceQuotaExceeded, ErrorCode::NotFound,
+        ErrorCode::NotImplemented, ErrorCode::DatabaseError, ErrorCode::ConflictError,
+        ErrorCode::BusinessRuleViolation, ErrorCode::PlanLimitExceeded, ErrorCode::RateLimitExceeded,
+        ErrorCode::TooManyRequests, ErrorCode::ConcurrencyLimitExceeded, ErrorCode::CacheError,
+        ErrorCode::CacheMiss, ErrorCode::StorageError, ErrorCode::EncryptionError,
+        ErrorCode::DecryptionError, ErrorCode::JobQueueError, ErrorCode::JobExecutionFailed,
+        ErrorCode::JobTimeout, ErrorCode::JobDeserializationError,
+    ];
+
     /// Get HTTP status code for this error
     pub fn http_status(&self) -> u16 {
         match self {
@@ -117,7 +141,8 @@ impl ErrorCode {
             // 403 - Forbidden
             ErrorCode::AuthorizationFailed
             | ErrorCode::PermissionDenied
-            | ErrorCode::SecurityPolicyViolation => 403,
+            | ErrorCode::SecurityPolicyViolation
+            | ErrorCode::PlanLimitExceeded => 403,
 
             // 400 - Bad Request
             ErrorCode::ValidationFailed
@@ -205,6 +230,7 @@ impl ErrorCode {
             | ErrorCode::ResourceLocked
             | ErrorCode::ResourceInUse
             | ErrorCode::ResourceQuotaExceeded
+            | ErrorCode::PlanLimitExceeded
             | ErrorCode::ConflictError => "resource",
 
             ErrorCode::RateLimitExceeded
@@ -254,6 +280,7 @@ impl ErrorCode {
                 | ErrorCode::AuthenticationFailed
                 | ErrorCode::InvalidCredentials
                 | ErrorCode::PermissionDenied
+                | ErrorCode::PlanLimitExceeded
                 | ErrorCode::RateLimitExceeded
                 | ErrorCode::TooManyRequests
         )
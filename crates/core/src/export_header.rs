@@ -0,0 +1,374 @@
+//! # Export Headers
+//!
+//! Exports that leave the company (statements, valuation reports, stock
+//! aging, audit trails) carry a standard header so an auditor can tell who
+//! produced them, when, and from which selection:
+//!
+//! - [`TenantLetterhead`] is the tenant's legal entity: legal name,
+//!   registration number, VAT id, registered address and a logo reference.
+//!   Only the legal name is required.
+//! - [`ExportHeader`] adds the export title, generation timestamp, requesting
+//!   user and applied filters. CSV exports prepend [`ExportHeader::csv_rows`]
+//!   as `#` comment rows; PDF reports render [`ExportHeader::letterhead_block`].
+//!
+//! Missing optional fields are left out rather than printed empty, and an
+//! export of a tenant without a letterhead still states its provenance.
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::types::address::{Address, AddressFormat};
+use crate::{Error, Result};
+
+/// Longest allowed legal name
+pub const MAX_LEGAL_NAME_LENGTH: usize = 200;
+/// Longest allowed registration number
+pub const MAX_REGISTRATION_NUMBER_LENGTH: usize = 50;
+/// Longest allowed logo reference
+pub const MAX_LOGO_REF_LENGTH: usize = 500;
+
+/// Two-letter country prefix followed by 2 to 13 letters or digits
+static VAT_ID: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[A-Z]{2}[A-Z0-9]{2,13}$").unwrap());
+static REGISTRATION_NUMBER: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[A-Za-z0-9][A-Za-z0-9 ./-]*$").unwrap());
+
+/// Legal entity details printed at the top of a tenant's exports
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TenantLetterhead {
+    pub legal_name: String,
+    /// Commercial register or company number
+    #[serde(default)]
+    pub registration_number: Option<String>,
+    #[serde(default)]
+    pub vat_id: Option<String>,
+    #[serde(default)]
+    pub registered_address: Option<Address>,
+    /// URL or storage key of the logo image
+    #[serde(default)]
+    pub logo_ref: Option<String>,
+}
+
+fn trimmed(value: &Option<String>) -> Option<String> {
+    value.as_deref().map(str::trim).filter(|v| !v.is_empty()).map(str::to_string)
+}
+
+impl TenantLetterhead {
+    /// Trim every field, drop empty optional ones, uppercase the VAT id and
+    /// normalize the address; fails on the first field that is invalid
+    pub fn normalize_and_validate(&self) -> Result<Self> {
+        let legal_name = self.legal_name.split_whitespace().collect::<Vec<_>>().join(" ");
+        if legal_name.is_empty() {
+            return Err(Error::validation("The legal name is required"));
+        }
+        if legal_name.chars().count() > MAX_LEGAL_NAME_LENGTH {
+            return Err(Error::validation(format!(
+                "The legal name is longer than {} characters",
+                MAX_LEGAL_NAME_LENGTH
+            )));
+        }
+
+        let registration_number = trimmed(&self.registration_number);
+        if let Some(number) = &registration_number {
+            if number.len() > MAX_REGISTRATION_NUMBER_LENGTH || !REGISTRATION_NUMBER.is_match(number) {
+                return Err(Error::validation(format!(
+                    "Registration number '{}' may only contain letters, digits, spaces, '.', '/' and '-' (at most {} characters)",
+                    number, MAX_REGISTRATION_NUMBER_LENGTH
+                )));
+            }
+        }
+
+        let vat_id = trimmed(&self.vat_id).map(|id| id.replace([' ', '.', '-'], "").to_ascii_uppercase());
+        if let Some(id) = &vat_id {
+            if !VAT_ID.is_match(id) {
+                return Err(Error::validation(format!(
+                    "VAT id '{}' must be a two-letter country prefix followed by 2 to 13 letters or digits",
+                    id
+                )));
+            }
+        }
+
+        let registered_address = match &self.registered_address {
+            Some(address) => Some(address.normalize_and_validate().map_err(|violations| {
+                let details: Vec<String> = violations
+                    .iter()
+                    .map(|v| format!("registered_address.{}: {}", v.field, v.message))
+                    .collect();
+                Error::validation(format!("Invalid registered address: {}", details.join("; ")))
+            })?),
+            None => None,
+        };
+
+        let logo_ref = trimmed(&self.logo_ref);
+        if let Some(logo) = &logo_ref {
+            if logo.len() > MAX_LOGO_REF_LENGTH || logo.chars().any(|c| c.is_whitespace() || c.is_control()) {
+                return Err(Error::validation(format!(
+                    "The logo reference must be a URL or storage key without spaces (at most {} characters)",
+                    MAX_LOGO_REF_LENGTH
+                )));
+            }
+        }
+
+        Ok(Self {
+            legal_name,
+            registration_number,
+            vat_id,
+            registered_address,
+            logo_ref,
+        })
+    }
+
+    /// Legal name, registration details and address as printed, one per line
+    pub fn entity_lines(&self) -> Vec<String> {
+        let mut lines = vec![self.legal_name.clone()];
+        if let Some(address) = &self.registered_address {
+            lines.extend(address.formatted_lines());
+        }
+        if let Some(number) = &self.registration_number {
+            lines.push(format!("Registration number: {}", number));
+        }
+        if let Some(vat_id) = &self.vat_id {
+            lines.push(format!("VAT id: {}", vat_id));
+        }
+        lines
+    }
+}
+
+/// Quote a CSV field when needed; a leading formula character is neutralized so
+/// exported values cannot run as spreadsheet formulas
+pub fn csv_field(value: &str) -> String {
+    let value = if value.starts_with(['=', '+', '-', '@']) {
+        format!("'{}", value)
+    } else {
+        value.to_string()
+    };
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+/// Letterhead of a PDF report: logo and entity lines on top, provenance below
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LetterheadBlock {
+    pub logo_ref: Option<String>,
+    /// Legal entity lines; empty when the tenant has no letterhead
+    pub entity: Vec<String>,
+    pub title: String,
+    /// Generation timestamp, requesting user and filters
+    pub provenance: Vec<String>,
+}
+
+/// Header of one export
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ExportHeader {
+    pub title: String,
+    pub letterhead: Option<TenantLetterhead>,
+    pub generated_at: DateTime<Utc>,
+    /// User the export was produced for
+    pub requested_by: String,
+    /// Filters in the order the caller applied them, as `(name, value)`
+    pub filters: Vec<(String, String)>,
+}
+
+impl ExportHeader {
+    pub fn new(title: impl Into<String>, letterhead: Option<TenantLetterhead>, requested_by: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            letterhead,
+            generated_at: Utc::now(),
+            requested_by: requested_by.into(),
+            filters: Vec::new(),
+        }
+    }
+
+    pub fn with_filter(mut self, name: impl Into<String>, value: impl ToString) -> Self {
+        self.filters.push((name.into(), value.to_string()));
+        self
+    }
+
+    /// Add the filter only when it was set
+    pub fn with_optional_filter(self, name: impl Into<String>, value: Option<impl ToString>) -> Self {
+        match value {
+            Some(value) => self.with_filter(name, value),
+            None => self,
+        }
+    }
+
+    fn provenance_lines(&self) -> Vec<String> {
+        let filters = if self.filters.is_empty() {
+            "none".to_string()
+        } else {
+            self.filters
+                .iter()
+                .map(|(name, value)| format!("{}={}", name, value))
+                .collect::<Vec<_>>()
+                .join("; ")
+        };
+        vec![
+            format!("Generated at: {}", self.generated_at.to_rfc3339_opts(SecondsFormat::Secs, true)),
+            format!("Requested by: {}", self.requested_by),
+            format!("Filters: {}", filters),
+        ]
+    }
+
+    /// Every header line, entity first
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = self.letterhead.as_ref().map(TenantLetterhead::entity_lines).unwrap_or_default();
+        lines.push(format!("Export: {}", self.title));
+        lines.extend(self.provenance_lines());
+        lines
+    }
+
+    /// The header as `#` comment rows, to put in front of the CSV column row
+    pub fn csv_rows(&self) -> String {
+        let mut rows = String::new();
+        for line in self.lines() {
+            // A line break inside a value would end the comment row early
+            let line: String = line.chars().map(|c| if c.is_control() { ' ' } else { c }).collect();
+            rows.push_str("# ");
+            rows.push_str(&line);
+            rows.push('\n');
+        }
+        rows
+    }
+
+    /// The header as a letterhead block for PDF reports
+    pub fn letterhead_block(&self) -> LetterheadBlock {
+        LetterheadBlock {
+            logo_ref: self.letterhead.as_ref().and_then(|l| l.logo_ref.clone()),
+            entity: self.letterhead.as_ref().map(TenantLetterhead::entity_lines).unwrap_or_default(),
+            title: self.title.clone(),
+            provenance: self.provenance_lines(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn letterhead() -> TenantLetterhead {
+        TenantLetterhead {
+            legal_name: "  Muster   Handel GmbH ".to_string(),
+            registration_number: Some(" HRB 12345 ".to_string()),
+            vat_id: Some("de 123 456 789".to_string()),
+            registered_address: Some(Address {
+                country_code: "de".to_string(),
+                lines: vec!["Hauptstraße 1".to_string()],
+                city: "Berlin".to_string(),
+                region: None,
+                postal_code: Some("10115".to_string()),
+                coordinates: None,
+            }),
+            logo_ref: Some("tenants/muster/logo.png".to_string()),
+        }
+    }
+
+    fn letterhead_only(name: &str) -> TenantLetterhead {
+        TenantLetterhead {
+            legal_name: name.to_string(),
+            registration_number: None,
+            vat_id: None,
+            registered_address: None,
+            logo_ref: None,
+        }
+    }
+
+    fn header(letterhead: Option<TenantLetterhead>) -> ExportHeader {
+        let mut header = ExportHeader::new("Stock aging", letterhead, "5f0c6a4e-0000-4000-8000-000000000001");
+        header.generated_at = Utc.with_ymd_and_hms(2026, 10, 17, 9, 30, 0).unwrap();
+        header
+    }
+
+    #[test]
+    fn test_letterhead_is_normalized() {
+        let letterhead = letterhead().normalize_and_validate().unwrap();
+        assert_eq!(letterhead.legal_name, "Muster Handel GmbH");
+        assert_eq!(letterhead.registration_number.as_deref(), Some("HRB 12345"));
+        assert_eq!(letterhead.vat_id.as_deref(), Some("DE123456789"));
+        assert_eq!(letterhead.registered_address.unwrap().country_code, "DE");
+
+        let blank = TenantLetterhead {
+            vat_id: Some("  ".to_string()),
+            logo_ref: Some(String::new()),
+            ..letterhead_only("Muster")
+        };
+        let blank = blank.normalize_and_validate().unwrap();
+        assert_eq!((blank.vat_id, blank.logo_ref), (None, None));
+    }
+
+    #[test]
+    fn test_invalid_letterheads_are_rejected() {
+        let invalid = [
+            letterhead_only("   "),
+            TenantLetterhead { vat_id: Some("123456".to_string()), ..letterhead() },
+            TenantLetterhead { registration_number: Some("HRB=1".to_string()), ..letterhead() },
+            TenantLetterhead { logo_ref: Some("my logo.png".to_string()), ..letterhead() },
+            TenantLetterhead {
+                registered_address: Some(Address { lines: vec![], ..letterhead().registered_address.unwrap() }),
+                ..letterhead()
+            },
+        ];
+        for letterhead in invalid {
+            assert!(letterhead.normalize_and_validate().is_err(), "{:?} should be rejected", letterhead);
+        }
+    }
+
+    #[test]
+    fn test_csv_rows_carry_entity_and_provenance() {
+        let header = header(Some(letterhead().normalize_and_validate().unwrap()))
+            .with_filter("location", "Main")
+            .with_optional_filter("since", Some("2026-01-01"))
+            .with_optional_filter("role", None::<&str>);
+
+        assert_eq!(
+            header.csv_rows(),
+            "# Muster Handel GmbH\n\
+             # Hauptstraße 1\n\
+             # 10115 Berlin\n\
+             # DE\n\
+             # Registration number: HRB 12345\n\
+             # VAT id: DE123456789\n\
+             # Export: Stock aging\n\
+             # Generated at: 2026-10-17T09:30:00Z\n\
+             # Requested by: 5f0c6a4e-0000-4000-8000-000000000001\n\
+             # Filters: location=Main; since=2026-01-01\n"
+        );
+    }
+
+    #[test]
+    fn test_missing_optional_fields_are_left_out() {
+        let minimal = header(Some(letterhead_only("Muster Handel GmbH")));
+        let rows = minimal.csv_rows();
+        assert!(rows.starts_with("# Muster Handel GmbH\n# Export: Stock aging\n"));
+        assert!(rows.contains("# Filters: none\n"));
+        assert!(!rows.contains("VAT") && !rows.contains("Registration"));
+
+        let block = minimal.letterhead_block();
+        assert_eq!(block.logo_ref, None);
+        assert_eq!(block.entity, vec!["Muster Handel GmbH"]);
+
+        // Without a letterhead the export still states its provenance
+        let anonymous = header(None);
+        assert!(anonymous.csv_rows().starts_with("# Export: Stock aging\n# Generated at: "));
+        assert!(anonymous.letterhead_block().entity.is_empty());
+    }
+
+    #[test]
+    fn test_line_breaks_cannot_end_a_comment_row() {
+        let rows = header(None).with_filter("q", "a\nrole,permission").csv_rows();
+        assert!(rows.lines().all(|line| line.starts_with("# ")));
+    }
+
+    #[test]
+    fn test_letterhead_block_for_pdf() {
+        let block = header(Some(letterhead().normalize_and_validate().unwrap())).letterhead_block();
+        assert_eq!(block.logo_ref.as_deref(), Some("tenants/muster/logo.png"));
+        assert_eq!(block.entity[0], "Muster Handel GmbH");
+        assert_eq!(block.title, "Stock aging");
+        assert_eq!(block.provenance[1], "Requested by: 5f0c6a4e-0000-4000-8000-000000000001");
+    }
+}
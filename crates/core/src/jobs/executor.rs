@@ -1,12 +1,14 @@
 use super::{
+    health::QueueHeartbeat,
     traits::{JobContext, JobHandler, JobQueue, JobResult},
     types::{JobId, JobState, QueuedJob},
 };
 use crate::error::{Error, ErrorCode, Result};
+use std::any::Any;
 use std::collections::HashMap;
 use std::sync::Arc;
-use std::time::Duration;
-use tokio::sync::{mpsc, RwLock, Semaphore};
+use std::time::{Duration, Instant};
+use tokio::sync::{watch, RwLock, Semaphore};
 use tokio::time::timeout;
 use tracing::{debug, error, info, warn};
 
@@ -19,6 +21,9 @@ pub struct ExecutorConfig {
     pub job_timeout: Duration,
     pub shutdown_timeout: Duration,
     pub enable_metrics: bool,
+    /// First delay before restarting a panicked worker loop; doubles per restart
+    pub restart_backoff: Duration,
+    pub max_restart_backoff: Duration,
 }
 
 impl Default for ExecutorConfig {
@@ -30,6 +35,8 @@ impl Default for ExecutorConfig {
             job_timeout: Duration::from_secs(300), // 5 minutes
             shutdown_timeout: Duration::from_secs(30),
             enable_metrics: true,
+            restart_backoff: Duration::from_secs(1),
+            max_restart_backoff: Duration::from_secs(60),
         }
     }
 }
@@ -39,7 +46,7 @@ pub struct JobExecutor {
     queue: Arc<dyn JobQueue>,
     handlers: Arc<RwLock<HashMap<String, Arc<dyn JobHandler>>>>,
     config: ExecutorConfig,
-    shutdown_tx: Option<mpsc::Sender<()>>,
+    shutdown_tx: Option<watch::Sender<bool>>,
     semaphore: Arc<Semaphore>,
     metrics: Arc<RwLock<ExecutorMetrics>>,
 }
@@ -52,6 +59,8 @@ struct ExecutorMetrics {
     jobs_retried: u64,
     total_processing_time: Duration,
     active_jobs: u64,
+    /// Worker loop restarts after a panic
+    restarts: u64,
 }
 
 impl ExecutorMetrics {
@@ -96,8 +105,11 @@ impl JobExecutor {
     }
 
     /// Start the executor (non-blocking)
+    ///
+    /// The worker loop runs under a watchdog that restarts it with backoff
+    /// when it panics.
     pub async fn start(&mut self) -> Result<()> {
-        let (shutdown_tx, shutdown_rx) = mpsc::channel(1);
+        let (shutdown_tx, shutdown_rx) = watch::channel(false);
         self.shutdown_tx = Some(shutdown_tx);
 
         let queue = Arc::clone(&self.queue);
@@ -107,7 +119,7 @@ impl JobExecutor {
         let metrics = Arc::clone(&self.metrics);
 
         tokio::spawn(async move {
-            Self::worker_loop(queue, handlers, config, semaphore, metrics, shutdown_rx).await;
+            Self::watchdog(queue, handlers, config, semaphore, metrics, shutdown_rx).await;
         });
 
         info!("Job executor started with worker ID: {}", self.config.worker_id);
@@ -117,12 +129,61 @@ impl JobExecutor {
     /// Stop the executor gracefully
     pub async fn stop(&mut self) -> Result<()> {
         if let Some(shutdown_tx) = self.shutdown_tx.take() {
-            let _ = shutdown_tx.send(()).await;
+            let _ = shutdown_tx.send(true);
             info!("Job executor shutdown signal sent");
         }
         Ok(())
     }
 
+    /// Run the worker loop, restarting it with exponential backoff whenever it panics
+    async fn watchdog(
+        queue: Arc<dyn JobQueue>,
+        handlers: Arc<RwLock<HashMap<String, Arc<dyn JobHandler>>>>,
+        config: ExecutorConfig,
+        semaphore: Arc<Semaphore>,
+        metrics: Arc<RwLock<ExecutorMetrics>>,
+        mut shutdown_rx: watch::Receiver<bool>,
+    ) {
+        let mut backoff = config.restart_backoff;
+
+        while !*shutdown_rx.borrow() {
+            let started = Instant::now();
+            let worker = tokio::spawn(Self::worker_loop(
+                Arc::clone(&queue),
+                Arc::clone(&handlers),
+                config.clone(),
+                Arc::clone(&semaphore),
+                Arc::clone(&metrics),
+                shutdown_rx.clone(),
+            ));
+
+            match worker.await {
+                Ok(()) => break,
+                Err(e) if e.is_panic() => {
+                    error!(
+                        "Worker loop {} panicked: {}; restarting in {:?}",
+                        config.worker_id,
+                        panic_message(e.into_panic().as_ref()),
+                        backoff
+                    );
+                }
+                Err(e) => error!("Worker loop {} was aborted: {}; restarting in {:?}", config.worker_id, e, backoff),
+            }
+            metrics.write().await.restarts += 1;
+
+            tokio::select! {
+                _ = shutdown_rx.changed() => break,
+                _ = tokio::time::sleep(backoff) => {}
+            }
+            // A loop that ran longer than the longest backoff starts over from the first one
+            backoff = if started.elapsed() > config.max_restart_backoff {
+                config.restart_backoff
+            } else {
+                (backoff * 2).min(config.max_restart_backoff)
+            };
+        }
+    }
+
     /// Main worker loop
     async fn worker_loop(
         queue: Arc<dyn JobQueue>,
@@ -130,7 +191,7 @@ impl JobExecutor {
         config: ExecutorConfig,
         semaphore: Arc<Semaphore>,
         metrics: Arc<RwLock<ExecutorMetrics>>,
-        mut shutdown_rx: mpsc::Receiver<()>,
+        mut shutdown_rx: watch::Receiver<bool>,
     ) {
         info!("Worker loop started: {}", config.worker_id);
         
@@ -139,11 +200,20 @@ impl JobExecutor {
 
         loop {
             tokio::select! {
-                _ = shutdown_rx.recv() => {
+                _ = shutdown_rx.changed() => {
                     info!("Shutdown signal received, stopping worker loop");
                     break;
                 }
                 _ = poll_interval.tick() => {
+                    let heartbeat = QueueHeartbeat {
+                        instance_id: config.worker_id.clone(),
+                        last_poll: chrono::Utc::now(),
+                        in_flight: metrics.read().await.active_jobs,
+                    };
+                    if let Err(e) = queue.record_heartbeat(&heartbeat).await {
+                        warn!("Failed to record heartbeat: {}", e);
+                    }
+
                     if let Err(e) = Self::process_next_job(
                         Arc::clone(&queue),
                         Arc::clone(&handlers),
@@ -380,6 +450,7 @@ impl JobExecutor {
             jobs_failed: metrics.jobs_failed,
             jobs_retried: metrics.jobs_retried,
             active_jobs: metrics.active_jobs,
+            restarts: metrics.restarts,
             success_rate: metrics.success_rate(),
             average_processing_time: metrics.average_processing_time(),
         }
@@ -401,10 +472,20 @@ pub struct ExecutorMetricsSnapshot {
     pub jobs_failed: u64,
     pub jobs_retried: u64,
     pub active_jobs: u64,
+    pub restarts: u64,
     pub success_rate: f64,
     pub average_processing_time: Duration,
 }
 
+/// Text of a panic payload, which is a `&str` or a `String` for `panic!` with a message
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "non-string panic payload".to_string())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -449,6 +530,7 @@ mod tests {
             jobs_retried: 3,
             total_processing_time: Duration::from_secs(1000),
             active_jobs: 2,
+            restarts: 0,
         };
 
         assert_eq!(metrics.success_rate(), 0.95);
@@ -500,4 +582,88 @@ mod tests {
         });
         assert!(handler.validate_job_data(&complex_data).is_ok());
     }
+    /// Queue whose first dequeue panics, like a worker loop hitting a bug
+    #[derive(Default)]
+    struct PanickingQueue {
+        polls: std::sync::atomic::AtomicU32,
+        heartbeats: std::sync::Mutex<Vec<QueueHeartbeat>>,
+    }
+
+    #[async_trait]
+    impl JobQueue for PanickingQueue {
+        async fn enqueue(&self, job: QueuedJob) -> Result<JobId> {
+            Ok(job.id)
+        }
+
+        async fn dequeue(&self, _worker_id: &str) -> Result<Option<QueuedJob>> {
+            if self.polls.fetch_add(1, std::sync::atomic::Ordering::SeqCst) == 0 {
+                panic!("consumer bug");
+            }
+            Ok(None)
+        }
+
+        async fn get_status(&self, _job_id: &JobId) -> Result<Option<crate::jobs::JobStatus>> {
+            Ok(None)
+        }
+
+        async fn update_status(&self, _job_id: &JobId, _status: crate::jobs::JobStatus) -> Result<()> {
+            Ok(())
+        }
+
+        async fn cancel_job(&self, _job_id: &JobId) -> Result<bool> {
+            Ok(false)
+        }
+
+        async fn get_stats(&self) -> Result<crate::jobs::traits::QueueStats> {
+            Ok(Default::default())
+        }
+
+        async fn cleanup_old_jobs(&self, _older_than: chrono::DateTime<chrono::Utc>) -> Result<u64> {
+            Ok(0)
+        }
+
+        async fn get_jobs_by_status(&self, _status: JobState, _limit: Option<u32>) -> Result<Vec<QueuedJob>> {
+            Ok(Vec::new())
+        }
+
+        async fn health_check(&self) -> Result<bool> {
+            Ok(true)
+        }
+
+        async fn record_heartbeat(&self, heartbeat: &QueueHeartbeat) -> Result<()> {
+            self.heartbeats.lock().unwrap().push(heartbeat.clone());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_watchdog_restarts_panicked_worker_loop() {
+        let queue = Arc::new(PanickingQueue::default());
+        let config = ExecutorConfig {
+            worker_id: "worker-test".to_string(),
+            poll_interval: Duration::from_millis(10),
+            restart_backoff: Duration::from_millis(20),
+            ..ExecutorConfig::default()
+        };
+        let mut executor = JobExecutor::new(queue.clone(), config);
+        executor.start().await.unwrap();
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        executor.stop().await.unwrap();
+
+        assert_eq!(executor.get_metrics().await.restarts, 1);
+        // The restarted loop kept polling and reporting its heartbeat
+        assert!(queue.polls.load(std::sync::atomic::Ordering::SeqCst) > 2);
+        let heartbeats = queue.heartbeats.lock().unwrap();
+        assert!(heartbeats.len() > 2);
+        assert!(heartbeats.iter().all(|h| h.instance_id == "worker-test" && h.in_flight == 0));
+    }
+
+    #[test]
+    fn test_panic_message() {
+        let payload = std::panic::catch_unwind(|| panic!("static message")).unwrap_err();
+        assert_eq!(panic_message(payload.as_ref()), "static message");
+        let payload = std::panic::catch_unwind(|| panic!("formatted {}", 42)).unwrap_err();
+        assert_eq!(panic_message(payload.as_ref()), "formatted 42");
+    }
 }
\ No newline at end of file
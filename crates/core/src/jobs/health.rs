@@ -0,0 +1,260 @@
+//! Job queue health
+//!
+//! A queue can look fine from Redis' point of view while nothing consumes it:
+//! a panicked executor loop leaves jobs piling up without an error anywhere.
+//! Executors therefore record a [`QueueHeartbeat`] on every poll, and the
+//! [`QueueHealthEvaluator`] flags a queue when
+//!
+//! - no consumer polled it within `stale_after_polls` poll intervals, or
+//! - its depth stayed above `depth_threshold` and kept growing for
+//!   `depth_growth_minutes`.
+//!
+//! The evaluator keeps the depth samples it needs for the second rule, so one
+//! instance should see every sample of a queue.
+
+use crate::config::JobHealthConfig;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+
+/// Last poll of one executor instance
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueueHeartbeat {
+    pub instance_id: String,
+    pub last_poll: DateTime<Utc>,
+    /// Jobs the instance was running at the time
+    pub in_flight: u64,
+}
+
+/// Jobs ready to run
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueueDepth {
+    pub queued: u64,
+    pub oldest_enqueued_at: Option<DateTime<Utc>>,
+}
+
+/// Why a queue is unhealthy
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "issue", rename_all = "snake_case")]
+pub enum QueueIssue {
+    /// No executor has polled the queue yet
+    NoConsumer,
+    /// The freshest heartbeat is older than the allowed number of polls
+    StaleHeartbeat { age_seconds: i64 },
+    /// Depth above the threshold and growing for the whole window
+    DepthGrowing { depth: u64, minutes: u64 },
+    /// The queue could not be read
+    Unavailable { error: String },
+}
+
+/// Health of one queue at one point in time
+#[derive(Debug, Clone, Serialize)]
+pub struct QueueHealth {
+    pub queue: String,
+    pub healthy: bool,
+    pub depth: u64,
+    pub oldest_job_age_seconds: Option<i64>,
+    /// Age of the freshest heartbeat
+    pub heartbeat_age_seconds: Option<i64>,
+    /// Instances with a fresh heartbeat
+    pub consumers: usize,
+    pub in_flight: u64,
+    pub issues: Vec<QueueIssue>,
+    pub checked_at: DateTime<Utc>,
+}
+
+impl QueueHealth {
+    /// Health of a queue that could not be read
+    pub fn unavailable(queue: &str, now: DateTime<Utc>, error: impl Into<String>) -> Self {
+        Self {
+            queue: queue.to_string(),
+            healthy: false,
+            depth: 0,
+            oldest_job_age_seconds: None,
+            heartbeat_age_seconds: None,
+            consumers: 0,
+            in_flight: 0,
+            issues: vec![QueueIssue::Unavailable { error: error.into() }],
+            checked_at: now,
+        }
+    }
+}
+
+/// Applies the heartbeat and depth rules, remembering depth samples per queue
+#[derive(Debug)]
+pub struct QueueHealthEvaluator {
+    config: JobHealthConfig,
+    samples: HashMap<String, VecDeque<(DateTime<Utc>, u64)>>,
+}
+
+impl QueueHealthEvaluator {
+    pub fn new(config: JobHealthConfig) -> Self {
+        Self {
+            config,
+            samples: HashMap::new(),
+        }
+    }
+
+    /// Heartbeats older than this no longer count as a consumer
+    pub fn stale_after(&self) -> Duration {
+        Duration::seconds((self.config.poll_interval_seconds * self.config.stale_after_polls as u64) as i64)
+    }
+
+    fn growth_window(&self) -> Duration {
+        Duration::minutes(self.config.depth_growth_minutes as i64)
+    }
+
+    /// Record a depth sample and evaluate the queue
+    pub fn evaluate(
+        &mut self,
+        queue: &str,
+        now: DateTime<Utc>,
+        depth: &QueueDepth,
+        heartbeats: &[QueueHeartbeat],
+    ) -> QueueHealth {
+        let mut issues = Vec::new();
+
+        let stale_after = self.stale_after();
+        let fresh: Vec<&QueueHeartbeat> = heartbeats.iter().filter(|h| now - h.last_poll <= stale_after).collect();
+        let heartbeat_age = heartbeats.iter().map(|h| (now - h.last_poll).num_seconds()).min();
+        match heartbeat_age {
+            None => issues.push(QueueIssue::NoConsumer),
+            Some(age) if fresh.is_empty() => issues.push(QueueIssue::StaleHeartbeat { age_seconds: age }),
+            Some(_) => {}
+        }
+
+        if self.record_sample(queue, now, depth.queued) {
+            issues.push(QueueIssue::DepthGrowing {
+                depth: depth.queued,
+                minutes: self.config.depth_growth_minutes,
+            });
+        }
+
+        QueueHealth {
+            queue: queue.to_string(),
+            healthy: issues.is_empty(),
+            depth: depth.queued,
+            oldest_job_age_seconds: depth.oldest_enqueued_at.map(|at| (now - at).num_seconds().max(0)),
+            heartbeat_age_seconds: heartbeat_age,
+            consumers: fresh.len(),
+            in_flight: fresh.iter().map(|h| h.in_flight).sum(),
+            issues,
+            checked_at: now,
+        }
+    }
+
+    /// Keep the samples of the growth window plus the last one before it, and
+    /// tell whether depth stayed above the threshold and grew across the window
+    fn record_sample(&mut self, queue: &str, now: DateTime<Utc>, depth: u64) -> bool {
+        let window_start = now - self.growth_window();
+        let samples = self.samples.entry(queue.to_string()).or_default();
+        samples.push_back((now, depth));
+        while samples.len() > 1 && samples[1].0 <= window_start {
+            samples.pop_front();
+        }
+
+        let (first_at, first_depth) = samples[0];
+        first_at <= window_start
+            && first_depth > self.config.depth_threshold
+            && depth > first_depth
+            && samples.iter().zip(samples.iter().skip(1)).all(|(a, b)| b.1 >= a.1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> JobHealthConfig {
+        JobHealthConfig {
+            poll_interval_seconds: 2,
+            stale_after_polls: 5,
+            depth_threshold: 100,
+            depth_growth_minutes: 10,
+            ..JobHealthConfig::default()
+        }
+    }
+
+    fn heartbeat(instance: &str, last_poll: DateTime<Utc>) -> QueueHeartbeat {
+        QueueHeartbeat {
+            instance_id: instance.to_string(),
+            last_poll,
+            in_flight: 2,
+        }
+    }
+
+    fn depth(queued: u64) -> QueueDepth {
+        QueueDepth {
+            queued,
+            oldest_enqueued_at: None,
+        }
+    }
+
+    #[test]
+    fn test_heartbeat_expires_after_allowed_polls() {
+        let mut evaluator = QueueHealthEvaluator::new(config());
+        let now = Utc::now();
+
+        let health = evaluator.evaluate("jobs", now, &depth(0), &[]);
+        assert_eq!(health.issues, vec![QueueIssue::NoConsumer]);
+
+        // 5 polls of 2 seconds: 10 seconds is still fresh, 11 is not
+        let health = evaluator.evaluate("jobs", now, &depth(0), &[heartbeat("a", now - Duration::seconds(10))]);
+        assert!(health.healthy);
+        assert_eq!(health.consumers, 1);
+        assert_eq!(health.in_flight, 2);
+
+        let health = evaluator.evaluate("jobs", now, &depth(0), &[heartbeat("a", now - Duration::seconds(11))]);
+        assert!(!health.healthy);
+        assert_eq!(health.issues, vec![QueueIssue::StaleHeartbeat { age_seconds: 11 }]);
+
+        // One live instance is enough
+        let health = evaluator.evaluate(
+            "jobs",
+            now,
+            &depth(0),
+            &[heartbeat("a", now - Duration::minutes(5)), heartbeat("b", now - Duration::seconds(1))],
+        );
+        assert!(health.healthy);
+        assert_eq!(health.consumers, 1);
+        assert_eq!(health.heartbeat_age_seconds, Some(1));
+    }
+
+    #[test]
+    fn test_monotonic_depth_growth_past_threshold() {
+        let mut evaluator = QueueHealthEvaluator::new(config());
+        let start = Utc::now();
+        let live = |at: DateTime<Utc>| vec![heartbeat("a", at)];
+
+        // Growing every minute from 101; only unhealthy once the whole 10 minutes were seen
+        for minute in 0..10 {
+            let at = start + Duration::minutes(minute);
+            let health = evaluator.evaluate("jobs", at, &depth(101 + minute as u64), &live(at));
+            assert!(health.healthy, "minute {}", minute);
+        }
+        let at = start + Duration::minutes(10);
+        let health = evaluator.evaluate("jobs", at, &depth(111), &live(at));
+        assert_eq!(health.issues, vec![QueueIssue::DepthGrowing { depth: 111, minutes: 10 }]);
+
+        // A single drop resets the rule until a full window of growth is seen again
+        let at = start + Duration::minutes(11);
+        assert!(evaluator.evaluate("jobs", at, &depth(105), &live(at)).healthy);
+        let at = start + Duration::minutes(20);
+        assert!(evaluator.evaluate("jobs", at, &depth(150), &live(at)).healthy);
+        let at = start + Duration::minutes(21);
+        assert!(!evaluator.evaluate("jobs", at, &depth(151), &live(at)).healthy);
+    }
+
+    #[test]
+    fn test_depth_below_threshold_or_flat_is_healthy() {
+        let mut evaluator = QueueHealthEvaluator::new(config());
+        let start = Utc::now();
+        for minute in 0..=20 {
+            let at = start + Duration::minutes(minute);
+            // Growing but below the threshold
+            assert!(evaluator.evaluate("small", at, &depth(10 + minute as u64), &[heartbeat("a", at)]).healthy);
+            // Above the threshold but not growing
+            assert!(evaluator.evaluate("flat", at, &depth(500), &[heartbeat("a", at)]).healthy);
+        }
+    }
+}
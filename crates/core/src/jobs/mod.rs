@@ -1,9 +1,11 @@
 pub mod executor;
+pub mod health;
 pub mod queue;
 pub mod traits;
 pub mod types;
 
 pub use executor::{JobExecutor, ExecutorConfig};
+pub use health::{QueueDepth, QueueHealth, QueueHealthEvaluator, QueueHeartbeat, QueueIssue};
 pub use queue::RedisJobQueue;
 pub use traits::JobQueue;
 pub use traits::{Job, JobHandler, JobResult};
@@ -1,3 +1,4 @@
+use super::health::{QueueDepth, QueueHeartbeat};
 use super::traits::{JobQueue, QueueStats};
 use super::types::{JobId, JobState, JobStatus, QueuedJob};
 use crate::error::{Error, ErrorCode, Result};
@@ -9,6 +10,9 @@ use serde_json;
 use std::collections::HashMap;
 use tracing::{debug, info, warn};
 
+/// Heartbeats of consumers silent for longer than this are dropped
+const HEARTBEAT_RETENTION_SECONDS: i64 = 3600;
+
 /// Redis-backed job queue implementation
 pub struct RedisJobQueue {
     redis: ConnectionManager,
@@ -16,6 +20,7 @@ pub struct RedisJobQueue {
     processing_set: String,
     job_data_prefix: String,
     stats_key: String,
+    heartbeats_key: String,
 }
 
 impl RedisJobQueue {
@@ -26,6 +31,7 @@ impl RedisJobQueue {
             processing_set: format!("{}:processing", queue_name),
             job_data_prefix: format!("{}:job:", queue_name),
             stats_key: format!("{}:stats", queue_name),
+            heartbeats_key: format!("{}:heartbeats", queue_name),
             queue_name,
         }
     }
@@ -430,4 +436,64 @@ impl JobQueue for RedisJobQueue {
         
         Ok(true)
     }
+
+    async fn record_heartbeat(&self, heartbeat: &QueueHeartbeat) -> Result<()> {
+        let mut conn = self.redis.clone();
+        let heartbeat_json = serde_json::to_string(heartbeat)
+            .map_err(|e| Error::new(ErrorCode::SerializationError, e.to_string()))?;
+
+        // One hash per queue, one field per instance; the hash goes once every consumer is gone
+        conn.hset::<_, _, _, ()>(&self.heartbeats_key, &heartbeat.instance_id, heartbeat_json).await?;
+        conn.expire::<_, ()>(&self.heartbeats_key, HEARTBEAT_RETENTION_SECONDS).await?;
+        Ok(())
+    }
+
+    async fn heartbeats(&self) -> Result<Vec<QueueHeartbeat>> {
+        let mut conn = self.redis.clone();
+        let entries: HashMap<String, String> = conn.hgetall(&self.heartbeats_key).await?;
+        let cutoff = Utc::now() - Duration::seconds(HEARTBEAT_RETENTION_SECONDS);
+
+        let mut heartbeats = Vec::with_capacity(entries.len());
+        for (instance_id, heartbeat_json) in entries {
+            match serde_json::from_str::<QueueHeartbeat>(&heartbeat_json) {
+                Ok(heartbeat) if heartbeat.last_poll >= cutoff => heartbeats.push(heartbeat),
+                _ => {
+                    debug!("Dropping old heartbeat of {} on {}", instance_id, self.queue_name);
+                    conn.hdel::<_, _, ()>(&self.heartbeats_key, &instance_id).await?;
+                }
+            }
+        }
+        heartbeats.sort_by(|a, b| a.instance_id.cmp(&b.instance_id));
+        Ok(heartbeats)
+    }
+
+    async fn depth(&self) -> Result<QueueDepth> {
+        let mut conn = self.redis.clone();
+        let mut depth = QueueDepth::default();
+
+        for priority in &[
+            super::types::JobPriority::Critical,
+            super::types::JobPriority::High,
+            super::types::JobPriority::Normal,
+            super::types::JobPriority::Low,
+        ] {
+            let queue_key = self.priority_queue_key(*priority);
+            let queued: u64 = conn.llen(&queue_key).await?;
+            if queued == 0 {
+                continue;
+            }
+            depth.queued += queued;
+
+            // Jobs are pushed on the left and popped on the right, so the oldest is last
+            let oldest: Option<String> = conn.lindex(&queue_key, -1).await?;
+            if let Some(job_id) = oldest {
+                if let Some(job) = self.load_job_data(&JobId::from_string(job_id)).await? {
+                    let created_at = job.status.created_at;
+                    depth.oldest_enqueued_at = Some(depth.oldest_enqueued_at.map_or(created_at, |at| at.min(created_at)));
+                }
+            }
+        }
+
+        Ok(depth)
+    }
 }
\ No newline at end of file
@@ -1,3 +1,4 @@
+use super::health::{QueueDepth, QueueHeartbeat};
 use super::types::{JobId, JobPriority, JobStatus, QueuedJob};
 use crate::error::Result;
 use async_trait::async_trait;
@@ -244,6 +245,24 @@ pub trait JobQueue: Send + Sync {
 
     /// Health check
     async fn health_check(&self) -> Result<bool>;
+
+    /// Record that a consumer polled the queue
+    async fn record_heartbeat(&self, _heartbeat: &QueueHeartbeat) -> Result<()> {
+        Ok(())
+    }
+
+    /// Latest heartbeat of every recently seen consumer
+    async fn heartbeats(&self) -> Result<Vec<QueueHeartbeat>> {
+        Ok(Vec::new())
+    }
+
+    /// Jobs ready to run and when the oldest of them was enqueued
+    async fn depth(&self) -> Result<QueueDepth> {
+        Ok(QueueDepth {
+            queued: self.get_stats().await?.queued_jobs,
+            oldest_enqueued_at: None,
+        })
+    }
 }
 
 /// Statistics about the job queue
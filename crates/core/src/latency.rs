@@ -0,0 +1,149 @@
+//! # Latency Budgets
+//!
+//! Service methods are timed against a soft budget of their method group, a
+//! name like `customer.search` or `product.write`. A method starts a
+//! [`LatencyTimer`] as its first statement; when the timer drops at the end of
+//! the method, the time spent is observed in the per-method histogram of
+//! [`LatencyMetrics`], and a method that took longer than its budget records a
+//! `latency budget exceeded` warning in the current span (the method's own,
+//! when it is `#[tracing::instrument]`ed) and counts it.
+//!
+//! Budgets are soft: the method is never interrupted. The budget of a group
+//! comes from `latency_budgets.groups` by its full name, then by its kind (the
+//! part after the module, `search` in `customer.search`), then
+//! `latency_budgets.default_ms` (see [`LatencyBudgetConfig`]).
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::config::LatencyBudgetConfig;
+use crate::metrics::LatencyMetrics;
+
+/// Soft latency budgets of a service's method groups
+#[derive(Debug, Clone, Default)]
+pub struct LatencyBudget {
+    config: Arc<LatencyBudgetConfig>,
+    metrics: Option<LatencyMetrics>,
+}
+
+impl LatencyBudget {
+    pub fn new(config: LatencyBudgetConfig) -> Self {
+        Self {
+            config: Arc::new(config),
+            metrics: None,
+        }
+    }
+
+    /// Observe method latencies and exceeded budgets in these metrics
+    pub fn with_metrics(mut self, metrics: LatencyMetrics) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Budget of a method group
+    pub fn limit(&self, group: &str) -> Duration {
+        Duration::from_millis(self.config.budget_ms(group))
+    }
+
+    /// Time a method of the group until the returned timer drops
+    pub fn start(&self, group: &'static str, method: &'static str) -> LatencyTimer<'_> {
+        LatencyTimer {
+            group,
+            method,
+            limit: self.limit(group),
+            metrics: self.metrics.as_ref(),
+            started: Instant::now(),
+        }
+    }
+}
+
+/// A running service method; checked against its budget on drop
+#[must_use = "the method is timed until the timer drops"]
+pub struct LatencyTimer<'a> {
+    group: &'static str,
+    method: &'static str,
+    limit: Duration,
+    metrics: Option<&'a LatencyMetrics>,
+    started: Instant,
+}
+
+impl LatencyTimer<'_> {
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+}
+
+impl Drop for LatencyTimer<'_> {
+    fn drop(&mut self) {
+        let elapsed = self.elapsed();
+        let exceeded = elapsed > self.limit;
+
+        if let Some(metrics) = self.metrics {
+            metrics
+                .method_duration_seconds
+                .with_label_values(&[self.group, self.method])
+                .observe(elapsed.as_secs_f64());
+            if exceeded {
+                metrics.budget_exceeded_total.with_label_values(&[self.group, self.method]).inc();
+            }
+        }
+
+        if exceeded {
+            tracing::warn!(
+                group = self.group,
+                method = self.method,
+                elapsed_ms = elapsed.as_millis() as u64,
+                budget_ms = self.limit.as_millis() as u64,
+                "latency budget exceeded"
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn budget() -> LatencyBudget {
+        LatencyBudget::new(LatencyBudgetConfig {
+            default_ms: 500,
+            groups: HashMap::from([("search".to_string(), 1000), ("customer.search".to_string(), 300)]),
+        })
+        .with_metrics(LatencyMetrics::new("test").unwrap())
+    }
+
+    #[test]
+    fn test_limit_by_group_then_kind_then_default() {
+        let budget = budget();
+        assert_eq!(budget.limit("customer.search"), Duration::from_millis(300));
+        assert_eq!(budget.limit("product.search"), Duration::from_millis(1000));
+        assert_eq!(budget.limit("product.write"), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn test_timer_observes_latency_and_counts_exceeded_budgets() {
+        let strict = LatencyBudget::new(LatencyBudgetConfig {
+            default_ms: 0,
+            groups: HashMap::new(),
+        })
+        .with_metrics(LatencyMetrics::new("test").unwrap());
+        {
+            let _timer = strict.start("customer.read", "get_customer");
+            std::thread::sleep(Duration::from_millis(2));
+        }
+
+        let metrics = strict.metrics.as_ref().unwrap();
+        let histogram = metrics.method_duration_seconds.with_label_values(&["customer.read", "get_customer"]);
+        assert_eq!(histogram.get_sample_count(), 1);
+        assert!(histogram.get_sample_sum() >= 0.002);
+        assert_eq!(metrics.budget_exceeded_total.with_label_values(&["customer.read", "get_customer"]).get(), 1);
+
+        // Within budget: observed, not counted
+        let budget = budget();
+        drop(budget.start("customer.read", "get_customer"));
+        let metrics = budget.metrics.as_ref().unwrap();
+        assert_eq!(metrics.method_duration_seconds.with_label_values(&["customer.read", "get_customer"]).get_sample_count(), 1);
+        assert_eq!(metrics.budget_exceeded_total.with_label_values(&["customer.read", "get_customer"]).get(), 0);
+    }
+}
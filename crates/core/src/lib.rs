@@ -1,22 +1,52 @@
+pub mod actor;
+pub mod anonymization;
+pub mod api_usage;
 pub mod audit;
+pub mod calendar;
 pub mod config;
 pub mod database;
+pub mod effective_config;
 pub mod error;
+pub mod export_header;
 pub mod jobs;
+pub mod latency;
 pub mod metrics;
+pub mod migration_audit;
+pub mod numbering;
+pub mod outbound;
+pub mod permission_usage;
+pub mod plan_limits;
+pub mod portal;
+pub mod retention;
+pub mod sandbox;
 pub mod security;
 pub mod session;
+pub mod tenant_resolver;
+pub mod tenant_template;
 pub mod types;
 pub mod utils;
+pub mod warnings;
 
+pub use actor::{ActorContext, SYSTEM_ACTOR_ID};
 pub use audit::{AuditEvent, AuditLogger, AuditRepository};
-pub use config::{Config, CorsConfig, EmailConfig};
+pub use config::{
+    ActivityConfig, AdminLiteConfig, ApiUsageConfig, ApiVersionDeprecation, ApiVersioningConfig, AuditWriterConfig, BackpressureConfig, BulkPricingConfig, Config, CorsConfig, CreditConfig,
+    CustomerEncryptionConfig, CustomerImportConfig, EgressConfig, EmailConfig, ExchangeRatesConfig, ExistenceChecksConfig, FollowUpReminderConfig, GlobalSearchConfig, GrpcConfig, IdempotencyConfig, InventoryAlertsConfig, InvitationConfig, JobHealthConfig, LatencyBudgetConfig, LocationCapacityConfig, MigrationAuditConfig, MonthEndSnapshotConfig, NotificationDigestConfig, NumberingConfig, NumberSequenceSettings, OutboundConfig, OutboundIntegrationConfig, OutboundIntegrationOverrides,
+    PasswordPolicyConfig, PermissionUsageConfig, PlanLimitsConfig, ProductLifecycleConfig, PurchaseReceivingConfig, QualityInspectionConfig, ReservationReconciliationConfig, RetentionConfig, ReturnsConfig, SandboxConfig, SyncConfig, TenantHealthConfig, TokenRefreshConfig, TransferApprovalConfig, WebhooksConfig,
+};
 pub use database::{DatabasePool, TenantPool};
+pub use effective_config::EffectiveConfig;
 pub use error::{Error, ErrorCode, ErrorContext, ErrorMetrics, Result};
 pub use jobs::{JobExecutor, JobQueue, RedisJobQueue, SerializableJob};
-pub use metrics::{AuthMetrics, MetricsRegistry, MetricsService};
-pub use session::{SessionManager, SessionData, SessionConfig, SessionState, SessionStats};
+pub use latency::{LatencyBudget, LatencyTimer};
+pub use metrics::{AuditMetrics, AuthMetrics, HttpMetrics, LatencyMetrics, MetricsRegistry, MetricsService, NumberingMetrics, OutboundMetrics};
+pub use session::{
+    SessionManager, SessionData, SessionConfig, SessionState, SessionStats, SessionLimit, SessionLimitStrategy,
+    CreatedSession,
+};
+pub use tenant_resolver::TenantContextResolver;
 pub use types::*;
+pub use warnings::{Warning, WarningCode};
 
 #[cfg(test)]
 mod tests;
@@ -0,0 +1,56 @@
+use prometheus::{Histogram, HistogramOpts, IntCounter, IntCounterVec, IntGauge, Opts, Registry};
+
+/// Metrics of the buffered audit writer
+#[derive(Debug, Clone)]
+pub struct AuditMetrics {
+    pub queue_depth: IntGauge,
+    pub batch_size: Histogram,
+    /// Labelled by severity and reason (`overflow`, `write_failed`)
+    pub dropped_total: IntCounterVec,
+    pub spilled_total: IntCounter,
+}
+
+impl AuditMetrics {
+    pub fn new(namespace: &str) -> Result<Self, prometheus::Error> {
+        let queue_depth = IntGauge::new(
+            format!("{}_audit_queue_depth", namespace),
+            "Audit events buffered and not yet written"
+        )?;
+
+        let batch_size = Histogram::with_opts(
+            HistogramOpts::new(
+                format!("{}_audit_batch_size", namespace),
+                "Audit events written per batch"
+            ).buckets(vec![1.0, 5.0, 10.0, 25.0, 50.0, 100.0, 200.0, 500.0, 1000.0]),
+        )?;
+
+        let dropped_total = IntCounterVec::new(
+            Opts::new(
+                format!("{}_audit_events_dropped_total", namespace),
+                "Total number of audit events that were never written"
+            ),
+            &["severity", "reason"]
+        )?;
+
+        let spilled_total = IntCounter::new(
+            format!("{}_audit_events_spilled_total", namespace),
+            "Total number of audit events spilled to Redis for a later replay"
+        )?;
+
+        Ok(Self {
+            queue_depth,
+            batch_size,
+            dropped_total,
+            spilled_total,
+        })
+    }
+
+    pub fn register_all(&self, registry: &Registry) -> Result<(), prometheus::Error> {
+        registry.register(Box::new(self.queue_depth.clone()))?;
+        registry.register(Box::new(self.batch_size.clone()))?;
+        registry.register(Box::new(self.dropped_total.clone()))?;
+        registry.register(Box::new(self.spilled_total.clone()))?;
+
+        Ok(())
+    }
+}
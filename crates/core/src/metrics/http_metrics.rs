@@ -0,0 +1,96 @@
+use prometheus::{HistogramVec, IntCounterVec, IntGauge, Opts, Registry};
+
+/// Metrics of the HTTP API, labelled by method and route template (`/api/v1/customers/:id`,
+/// not the requested path) so the series stay bounded
+#[derive(Debug, Clone)]
+pub struct HttpMetrics {
+    pub request_duration_seconds: HistogramVec,
+    /// Responses with a 4xx status, by method and route
+    pub client_errors_total: IntCounterVec,
+    /// Responses with a 5xx status, by method and route
+    pub server_errors_total: IntCounterVec,
+    /// Database connections checked out, updated on every scrape
+    pub db_pool_active_connections: IntGauge,
+}
+
+impl HttpMetrics {
+    pub fn new(namespace: &str) -> Result<Self, prometheus::Error> {
+        let request_duration_seconds = HistogramVec::new(
+            prometheus::HistogramOpts::new(
+                format!("{}_http_request_duration_seconds", namespace),
+                "Time spent answering HTTP requests"
+            ).buckets(vec![0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0]),
+            &["method", "route", "status"]
+        )?;
+
+        let client_errors_total = IntCounterVec::new(
+            Opts::new(
+                format!("{}_http_client_errors_total", namespace),
+                "Total number of HTTP responses with a 4xx status"
+            ),
+            &["method", "route"]
+        )?;
+
+        let server_errors_total = IntCounterVec::new(
+            Opts::new(
+                format!("{}_http_server_errors_total", namespace),
+                "Total number of HTTP responses with a 5xx status"
+            ),
+            &["method", "route"]
+        )?;
+
+        let db_pool_active_connections = IntGauge::new(
+            format!("{}_db_pool_active_connections", namespace),
+            "Database connections currently checked out of the pools"
+        )?;
+
+        Ok(Self {
+            request_duration_seconds,
+            client_errors_total,
+            server_errors_total,
+            db_pool_active_connections,
+        })
+    }
+
+    /// Records one answered request
+    pub fn observe(&self, method: &str, route: &str, status: u16, seconds: f64) {
+        self.request_duration_seconds
+            .with_label_values(&[method, route, &status.to_string()])
+            .observe(seconds);
+        match status {
+            400..=499 => self.client_errors_total.with_label_values(&[method, route]).inc(),
+            500..=599 => self.server_errors_total.with_label_values(&[method, route]).inc(),
+            _ => {}
+        }
+    }
+
+    pub fn register_all(&self, registry: &Registry) -> Result<(), prometheus::Error> {
+        registry.register(Box::new(self.request_duration_seconds.clone()))?;
+        registry.register(Box::new(self.client_errors_total.clone()))?;
+        registry.register(Box::new(self.server_errors_total.clone()))?;
+        registry.register(Box::new(self.db_pool_active_connections.clone()))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_errors_are_counted_by_status_class() {
+        let metrics = HttpMetrics::new("test").unwrap();
+        metrics.observe("GET", "/api/v1/customers/:id", 200, 0.01);
+        metrics.observe("GET", "/api/v1/customers/:id", 404, 0.01);
+        metrics.observe("POST", "/api/v1/customers", 503, 0.2);
+
+        assert_eq!(
+            metrics.request_duration_seconds.with_label_values(&["GET", "/api/v1/customers/:id", "200"]).get_sample_count(),
+            1
+        );
+        assert_eq!(metrics.client_errors_total.with_label_values(&["GET", "/api/v1/customers/:id"]).get(), 1);
+        assert_eq!(metrics.server_errors_total.with_label_values(&["GET", "/api/v1/customers/:id"]).get(), 0);
+        assert_eq!(metrics.server_errors_total.with_label_values(&["POST", "/api/v1/customers"]).get(), 1);
+    }
+}
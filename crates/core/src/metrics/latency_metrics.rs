@@ -0,0 +1,41 @@
+use prometheus::{HistogramVec, IntCounterVec, Opts, Registry};
+
+/// Metrics of service methods timed against their latency budget, labelled by
+/// method group (for example `customer.search`) and method
+#[derive(Debug, Clone)]
+pub struct LatencyMetrics {
+    pub method_duration_seconds: HistogramVec,
+    pub budget_exceeded_total: IntCounterVec,
+}
+
+impl LatencyMetrics {
+    pub fn new(namespace: &str) -> Result<Self, prometheus::Error> {
+        let method_duration_seconds = HistogramVec::new(
+            prometheus::HistogramOpts::new(
+                format!("{}_service_method_duration_seconds", namespace),
+                "Time spent in service methods"
+            ).buckets(vec![0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0]),
+            &["group", "method"]
+        )?;
+
+        let budget_exceeded_total = IntCounterVec::new(
+            Opts::new(
+                format!("{}_service_latency_budget_exceeded_total", namespace),
+                "Total number of service method calls that took longer than their soft latency budget"
+            ),
+            &["group", "method"]
+        )?;
+
+        Ok(Self {
+            method_duration_seconds,
+            budget_exceeded_total,
+        })
+    }
+
+    pub fn register_all(&self, registry: &Registry) -> Result<(), prometheus::Error> {
+        registry.register(Box::new(self.method_duration_seconds.clone()))?;
+        registry.register(Box::new(self.budget_exceeded_total.clone()))?;
+
+        Ok(())
+    }
+}
@@ -1,5 +1,15 @@
+pub mod audit_metrics;
 pub mod auth_metrics;
+pub mod http_metrics;
+pub mod latency_metrics;
+pub mod numbering_metrics;
+pub mod outbound_metrics;
 pub mod registry;
 
+pub use audit_metrics::AuditMetrics;
 pub use auth_metrics::AuthMetrics;
-pub use registry::{MetricsRegistry, MetricsService};
\ No newline at end of file
+pub use http_metrics::HttpMetrics;
+pub use latency_metrics::LatencyMetrics;
+pub use numbering_metrics::NumberingMetrics;
+pub use outbound_metrics::OutboundMetrics;
+pub use registry::{MetricsRegistry, MetricsService};
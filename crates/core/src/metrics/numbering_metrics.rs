@@ -0,0 +1,42 @@
+use prometheus::{IntCounterVec, Opts, Registry};
+
+/// Metrics of block-allocated number sequences, labelled by sequence name
+#[derive(Debug, Clone)]
+pub struct NumberingMetrics {
+    /// Blocks leased; `source` is `fresh` (advanced the sequence) or `returned` (reused a returned block)
+    pub block_refills_total: IntCounterVec,
+    /// Unused numbers given back on graceful shutdown
+    pub numbers_returned_total: IntCounterVec,
+}
+
+impl NumberingMetrics {
+    pub fn new(namespace: &str) -> Result<Self, prometheus::Error> {
+        let block_refills_total = IntCounterVec::new(
+            Opts::new(
+                format!("{}_number_block_refills_total", namespace),
+                "Total number of number blocks leased by this instance"
+            ),
+            &["sequence", "source"]
+        )?;
+
+        let numbers_returned_total = IntCounterVec::new(
+            Opts::new(
+                format!("{}_numbers_returned_total", namespace),
+                "Total number of unused leased numbers returned on shutdown"
+            ),
+            &["sequence"]
+        )?;
+
+        Ok(Self {
+            block_refills_total,
+            numbers_returned_total,
+        })
+    }
+
+    pub fn register_all(&self, registry: &Registry) -> Result<(), prometheus::Error> {
+        registry.register(Box::new(self.block_refills_total.clone()))?;
+        registry.register(Box::new(self.numbers_returned_total.clone()))?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,53 @@
+use prometheus::{HistogramVec, IntCounterVec, IntGaugeVec, Opts, Registry};
+
+/// Metrics of outbound HTTP calls, labelled by integration and destination host
+#[derive(Debug, Clone)]
+pub struct OutboundMetrics {
+    /// Completed attempts; `status` is the HTTP status or `timeout`, `error`, `blocked`, `circuit_open`
+    pub requests_total: IntCounterVec,
+    pub request_duration_seconds: HistogramVec,
+    /// 0 = closed, 1 = half-open, 2 = open
+    pub breaker_state: IntGaugeVec,
+}
+
+impl OutboundMetrics {
+    pub fn new(namespace: &str) -> Result<Self, prometheus::Error> {
+        let requests_total = IntCounterVec::new(
+            Opts::new(
+                format!("{}_outbound_requests_total", namespace),
+                "Total number of outbound HTTP request attempts"
+            ),
+            &["integration", "host", "status"]
+        )?;
+
+        let request_duration_seconds = HistogramVec::new(
+            prometheus::HistogramOpts::new(
+                format!("{}_outbound_request_duration_seconds", namespace),
+                "Time spent on outbound HTTP request attempts"
+            ).buckets(vec![0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0]),
+            &["integration", "host"]
+        )?;
+
+        let breaker_state = IntGaugeVec::new(
+            Opts::new(
+                format!("{}_outbound_breaker_state", namespace),
+                "Circuit breaker state per destination (0 closed, 1 half-open, 2 open)"
+            ),
+            &["integration", "host"]
+        )?;
+
+        Ok(Self {
+            requests_total,
+            request_duration_seconds,
+            breaker_state,
+        })
+    }
+
+    pub fn register_all(&self, registry: &Registry) -> Result<(), prometheus::Error> {
+        registry.register(Box::new(self.requests_total.clone()))?;
+        registry.register(Box::new(self.request_duration_seconds.clone()))?;
+        registry.register(Box::new(self.breaker_state.clone()))?;
+
+        Ok(())
+    }
+}
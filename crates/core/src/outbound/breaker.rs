@@ -0,0 +1,160 @@
+//! Circuit breaker for one destination host
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakerState {
+    /// Calls pass through; failures are counted
+    Closed,
+    /// The open period is over and one probe call is in flight
+    HalfOpen,
+    /// Calls are rejected until the open period is over
+    Open,
+}
+
+impl BreakerState {
+    /// Value exported on the `outbound_breaker_state` gauge
+    pub fn as_gauge(self) -> i64 {
+        match self {
+            Self::Closed => 0,
+            Self::HalfOpen => 1,
+            Self::Open => 2,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Inner {
+    state: BreakerState,
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+}
+
+/// Opens after `failure_threshold` consecutive failures and rejects calls for
+/// `open_for`. The first call after that is let through as a probe: success
+/// closes the breaker, failure opens it for another period.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    failure_threshold: u32,
+    open_for: Duration,
+    inner: Mutex<Inner>,
+}
+
+impl CircuitBreaker {
+    pub fn new(failure_threshold: u32, open_for: Duration) -> Self {
+        Self {
+            failure_threshold: failure_threshold.max(1),
+            open_for,
+            inner: Mutex::new(Inner {
+                state: BreakerState::Closed,
+                consecutive_failures: 0,
+                opened_at: None,
+            }),
+        }
+    }
+
+    /// Ask to make a call at `now`; `false` means the breaker rejects it
+    pub fn try_acquire(&self, now: Instant) -> bool {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        match inner.state {
+            BreakerState::Closed => true,
+            // Only one probe at a time
+            BreakerState::HalfOpen => false,
+            BreakerState::Open => {
+                let elapsed = inner.opened_at.map(|at| now.saturating_duration_since(at));
+                if elapsed.is_some_and(|elapsed| elapsed >= self.open_for) {
+                    inner.state = BreakerState::HalfOpen;
+                    true
+                } else {
+                    false
+                }
+            }
+        }
+    }
+
+    pub fn record_success(&self) {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        inner.state = BreakerState::Closed;
+        inner.consecutive_failures = 0;
+        inner.opened_at = None;
+    }
+
+    pub fn record_failure(&self, now: Instant) {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        inner.consecutive_failures = inner.consecutive_failures.saturating_add(1);
+        if inner.state == BreakerState::HalfOpen || inner.consecutive_failures >= self.failure_threshold {
+            inner.state = BreakerState::Open;
+            inner.opened_at = Some(now);
+        }
+    }
+
+    pub fn state(&self) -> BreakerState {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner()).state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_opens_after_consecutive_failures() {
+        let breaker = CircuitBreaker::new(3, Duration::from_secs(30));
+        let now = Instant::now();
+
+        breaker.record_failure(now);
+        breaker.record_failure(now);
+        assert!(breaker.try_acquire(now));
+        breaker.record_failure(now);
+
+        assert_eq!(breaker.state(), BreakerState::Open);
+        assert!(!breaker.try_acquire(now + Duration::from_secs(29)));
+    }
+
+    #[test]
+    fn test_success_resets_failure_count() {
+        let breaker = CircuitBreaker::new(2, Duration::from_secs(30));
+        let now = Instant::now();
+
+        breaker.record_failure(now);
+        breaker.record_success();
+        breaker.record_failure(now);
+
+        assert_eq!(breaker.state(), BreakerState::Closed);
+    }
+
+    #[test]
+    fn test_recovers_through_half_open_probe() {
+        let breaker = CircuitBreaker::new(1, Duration::from_secs(30));
+        let opened = Instant::now();
+        breaker.record_failure(opened);
+
+        let later = opened + Duration::from_secs(30);
+        assert!(breaker.try_acquire(later));
+        assert_eq!(breaker.state(), BreakerState::HalfOpen);
+        // A second caller waits for the probe
+        assert!(!breaker.try_acquire(later));
+
+        breaker.record_success();
+        assert_eq!(breaker.state(), BreakerState::Closed);
+        assert!(breaker.try_acquire(later));
+    }
+
+    #[test]
+    fn test_failed_probe_reopens() {
+        let breaker = CircuitBreaker::new(5, Duration::from_secs(30));
+        let opened = Instant::now();
+        for _ in 0..5 {
+            breaker.record_failure(opened);
+        }
+
+        let probe_at = opened + Duration::from_secs(31);
+        assert!(breaker.try_acquire(probe_at));
+        breaker.record_failure(probe_at);
+
+        assert_eq!(breaker.state(), BreakerState::Open);
+        assert!(!breaker.try_acquire(probe_at + Duration::from_secs(29)));
+        assert!(breaker.try_acquire(probe_at + Duration::from_secs(30)));
+    }
+}
@@ -0,0 +1,196 @@
+//! Egress rules: which hosts and addresses outbound calls may reach
+
+use crate::config::EgressConfig;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Arc;
+
+use super::OutboundError;
+
+/// Egress rules compiled from [`EgressConfig`]
+#[derive(Debug, Clone, Default)]
+pub struct EgressPolicy {
+    allow_private_networks: bool,
+    allowed_hosts: Vec<String>,
+    denied_hosts: Vec<String>,
+}
+
+impl EgressPolicy {
+    pub fn new(config: &EgressConfig) -> Self {
+        let normalize = |hosts: &[String]| hosts.iter().map(|h| h.trim().to_ascii_lowercase()).collect();
+        Self {
+            allow_private_networks: config.allow_private_networks,
+            allowed_hosts: normalize(&config.allowed_hosts),
+            denied_hosts: normalize(&config.denied_hosts),
+        }
+    }
+
+    /// Check a host name (or IP literal) before any connection is made
+    pub fn check_host(&self, host: &str) -> Result<(), OutboundError> {
+        let host = host.trim_start_matches('[').trim_end_matches(']').to_ascii_lowercase();
+        if matches_any(&self.denied_hosts, &host) {
+            return Err(blocked(&host, "host is on the egress deny list"));
+        }
+        if let Ok(ip) = host.parse::<IpAddr>() {
+            self.check_addr(&host, ip)?;
+        }
+        Ok(())
+    }
+
+    /// Check an address a host resolved to
+    pub fn check_addr(&self, host: &str, ip: IpAddr) -> Result<(), OutboundError> {
+        if self.allow_private_networks || matches_any(&self.allowed_hosts, host) || is_public(ip) {
+            Ok(())
+        } else {
+            Err(blocked(host, &format!("{} is not a public address", ip)))
+        }
+    }
+}
+
+fn blocked(host: &str, reason: &str) -> OutboundError {
+    OutboundError::Blocked {
+        host: host.to_string(),
+        reason: reason.to_string(),
+    }
+}
+
+/// Exact match, or subdomain match for entries written as `.example.com`
+fn matches_any(entries: &[String], host: &str) -> bool {
+    entries.iter().any(|entry| match entry.strip_prefix('.') {
+        Some(domain) => host == domain || host.ends_with(entry.as_str()),
+        None => host == entry,
+    })
+}
+
+/// Whether an address is routable on the public internet
+pub fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(mapped) => is_public_v4(mapped),
+            None => is_public_v6(ip),
+        },
+    }
+}
+
+fn is_public_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_private()
+        // Includes 169.254.169.254, the cloud metadata endpoint
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_multicast()
+        || a == 0
+        // Shared address space (carrier-grade NAT), 100.64.0.0/10
+        || (a == 100 && (64..128).contains(&b))
+        // IETF protocol assignments, 192.0.0.0/24
+        || (a == 192 && b == 0 && ip.octets()[2] == 0)
+        // Benchmarking, 198.18.0.0/15
+        || (a == 198 && (b == 18 || b == 19))
+        // Reserved, 240.0.0.0/4
+        || a >= 240)
+}
+
+fn is_public_v6(ip: Ipv6Addr) -> bool {
+    let first = ip.segments()[0];
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_multicast()
+        // Unique local, fc00::/7
+        || (first & 0xfe00) == 0xfc00
+        // Link-local, fe80::/10
+        || (first & 0xffc0) == 0xfe80
+        // Documentation, 2001:db8::/32
+        || (first == 0x2001 && ip.segments()[1] == 0x0db8))
+}
+
+/// DNS resolver that drops addresses the egress policy refuses.
+///
+/// Checking at resolution time (rather than once before the request) means a
+/// host cannot pass the check and then connect to a private address through a
+/// second lookup or a redirect.
+pub(super) struct GuardedResolver {
+    pub(super) policy: Arc<EgressPolicy>,
+}
+
+impl Resolve for GuardedResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let policy = self.policy.clone();
+        Box::pin(async move {
+            let host = name.as_str().to_ascii_lowercase();
+            policy.check_host(&host)?;
+
+            let resolved: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), 0)).await?.collect();
+            let mut refused = None;
+            let allowed: Vec<SocketAddr> = resolved
+                .into_iter()
+                .filter(|addr| match policy.check_addr(&host, addr.ip()) {
+                    Ok(()) => true,
+                    Err(e) => {
+                        refused = Some(e);
+                        false
+                    }
+                })
+                .collect();
+
+            match (allowed.is_empty(), refused) {
+                (true, Some(e)) => Err(e.into()),
+                _ => Ok(Box::new(allowed.into_iter()) as Addrs),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(allow_private: bool, allowed: &[&str], denied: &[&str]) -> EgressPolicy {
+        EgressPolicy::new(&EgressConfig {
+            allow_private_networks: allow_private,
+            allowed_hosts: allowed.iter().map(|h| h.to_string()).collect(),
+            denied_hosts: denied.iter().map(|h| h.to_string()).collect(),
+        })
+    }
+
+    #[test]
+    fn test_private_and_metadata_addresses_are_not_public() {
+        for ip in [
+            "127.0.0.1", "10.1.2.3", "172.16.0.1", "192.168.1.1", "169.254.169.254", "0.0.0.0",
+            "100.64.0.1", "::1", "fd00::1", "fe80::1", "::ffff:10.0.0.1",
+        ] {
+            assert!(!is_public(ip.parse().unwrap()), "{} should not be public", ip);
+        }
+        for ip in ["8.8.8.8", "1.1.1.1", "2606:4700:4700::1111"] {
+            assert!(is_public(ip.parse().unwrap()), "{} should be public", ip);
+        }
+    }
+
+    #[test]
+    fn test_private_ip_literal_is_blocked() {
+        let policy = policy(false, &[], &[]);
+        assert!(matches!(policy.check_host("169.254.169.254"), Err(OutboundError::Blocked { .. })));
+        assert!(matches!(policy.check_host("[::1]"), Err(OutboundError::Blocked { .. })));
+        assert!(policy.check_host("8.8.8.8").is_ok());
+        assert!(policy.check_host("partner.example.com").is_ok());
+    }
+
+    #[test]
+    fn test_private_addresses_can_be_allowed() {
+        let ip: IpAddr = "10.0.0.5".parse().unwrap();
+        assert!(policy(true, &[], &[]).check_addr("erp-tax.internal", ip).is_ok());
+        assert!(policy(false, &["erp-tax.internal"], &[]).check_addr("erp-tax.internal", ip).is_ok());
+        assert!(policy(false, &["erp-tax.internal"], &[]).check_addr("other.internal", ip).is_err());
+    }
+
+    #[test]
+    fn test_deny_list_wins_and_matches_subdomains() {
+        let policy = policy(true, &[".example.com"], &[".internal.example.com", "metadata.google.internal"]);
+        assert!(policy.check_host("metadata.google.internal").is_err());
+        assert!(policy.check_host("db.internal.example.com").is_err());
+        assert!(policy.check_host("api.example.com").is_ok());
+    }
+}
@@ -0,0 +1,350 @@
+//! # Permission Usage
+//!
+//! Which permissions each role actually exercises, for least-privilege reviews.
+//!
+//! `require_permission` reports every access it grants to the
+//! [`PermissionUsageRecorder`] found in the request extensions. Recording never
+//! waits on I/O: uses go into a bounded channel and are dropped, and counted,
+//! when it is full. The API server drains the channel into per-day Redis
+//! counters and flushes finished days into `permission_usage_daily`.
+//! [`PermissionUsageReport::build`] combines those counts with the tenant's
+//! roles to show permissions held but never used, and permissions used only
+//! under an admin override (an administrator impersonating the user).
+
+use chrono::{NaiveDate, Utc};
+use prometheus::{IntCounter, Opts};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+use crate::export_header::{csv_field, ExportHeader};
+
+/// One access granted by `require_permission`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PermissionUse {
+    pub tenant_id: Uuid,
+    pub user_id: Uuid,
+    pub permission: String,
+    /// Granted on a token an administrator obtained by impersonating the user
+    pub via_override: bool,
+    pub date: NaiveDate,
+}
+
+/// Fire-and-forget sink for permission uses; cheap to clone
+#[derive(Clone)]
+pub struct PermissionUsageRecorder {
+    sender: mpsc::Sender<PermissionUse>,
+    dropped: IntCounter,
+}
+
+impl PermissionUsageRecorder {
+    /// Recorder and the receiving end of its channel of `capacity` uses
+    pub fn new(capacity: usize, namespace: &str) -> Result<(Self, mpsc::Receiver<PermissionUse>), String> {
+        let dropped = IntCounter::with_opts(Opts::new(
+            format!("{}_permission_usage_dropped_total", namespace),
+            "Permission uses not recorded because the usage channel was full",
+        ))
+        .map_err(|e| e.to_string())?;
+        let (sender, receiver) = mpsc::channel(capacity.max(1));
+        Ok((Self { sender, dropped }, receiver))
+    }
+
+    /// Queue a use without waiting; dropped when the channel is full or closed
+    pub fn record(&self, tenant_id: Uuid, user_id: Uuid, permission: &str, via_override: bool) {
+        let usage = PermissionUse {
+            tenant_id,
+            user_id,
+            permission: permission.to_string(),
+            via_override,
+            date: Utc::now().date_naive(),
+        };
+        if self.sender.try_send(usage).is_err() {
+            self.dropped.inc();
+        }
+    }
+
+    /// Counter to register with the metrics registry
+    pub fn dropped_counter(&self) -> IntCounter {
+        self.dropped.clone()
+    }
+}
+
+/// Uses of one permission by one user, summed over a window
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UserPermissionUsage {
+    pub user_id: Uuid,
+    pub permission: String,
+    pub uses: i64,
+    pub override_uses: i64,
+    pub last_used: NaiveDate,
+}
+
+/// A role with its permissions and members
+#[derive(Debug, Clone)]
+pub struct RoleGrants {
+    pub role_id: Uuid,
+    pub role: String,
+    pub permissions: Vec<String>,
+    pub members: Vec<Uuid>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PermissionUsageStatus {
+    Used,
+    /// Held by the role, but no member used it in the window
+    Unused,
+    /// Members only used it while an administrator impersonated them
+    OverrideOnly,
+}
+
+impl PermissionUsageStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PermissionUsageStatus::Used => "used",
+            PermissionUsageStatus::Unused => "unused",
+            PermissionUsageStatus::OverrideOnly => "override_only",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RolePermissionUsage {
+    pub permission: String,
+    /// False when members used it through another role
+    pub held: bool,
+    pub status: PermissionUsageStatus,
+    pub uses: i64,
+    pub override_uses: i64,
+    /// Members who used it
+    pub users: usize,
+    pub last_used: Option<NaiveDate>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RoleUsage {
+    pub role_id: Uuid,
+    pub role: String,
+    pub members: usize,
+    pub permissions: Vec<RolePermissionUsage>,
+    /// Held but never exercised: candidates for removal
+    pub unused: Vec<String>,
+    pub override_only: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PermissionUsageReport {
+    pub tenant_id: Uuid,
+    pub since: NaiveDate,
+    pub roles: Vec<RoleUsage>,
+}
+
+impl PermissionUsageReport {
+    /// Attribute each member's usage to every role they hold
+    pub fn build(tenant_id: Uuid, since: NaiveDate, roles: Vec<RoleGrants>, usage: &[UserPermissionUsage]) -> Self {
+        let mut by_user: HashMap<Uuid, Vec<&UserPermissionUsage>> = HashMap::new();
+        for row in usage {
+            by_user.entry(row.user_id).or_default().push(row);
+        }
+
+        let roles = roles
+            .into_iter()
+            .map(|role| {
+                let held: HashSet<&str> = role.permissions.iter().map(String::as_str).collect();
+                let mut permissions: BTreeMap<String, RolePermissionUsage> = role
+                    .permissions
+                    .iter()
+                    .map(|p| (p.clone(), RolePermissionUsage::empty(p, true)))
+                    .collect();
+
+                for row in role.members.iter().filter_map(|m| by_user.get(m)).flatten() {
+                    let entry = permissions
+                        .entry(row.permission.clone())
+                        .or_insert_with(|| RolePermissionUsage::empty(&row.permission, held.contains(row.permission.as_str())));
+                    entry.uses += row.uses;
+                    entry.override_uses += row.override_uses;
+                    entry.users += 1;
+                    entry.last_used = entry.last_used.max(Some(row.last_used));
+                }
+
+                let permissions: Vec<RolePermissionUsage> = permissions
+                    .into_values()
+                    .map(|mut p| {
+                        p.status = match (p.uses, p.override_uses) {
+                            (0, 0) => PermissionUsageStatus::Unused,
+                            (0, _) => PermissionUsageStatus::OverrideOnly,
+                            _ => PermissionUsageStatus::Used,
+                        };
+                        p
+                    })
+                    .collect();
+                let with_status = |status| {
+                    permissions.iter().filter(|p| p.status == status).map(|p| p.permission.clone()).collect()
+                };
+
+                RoleUsage {
+                    role_id: role.role_id,
+                    unused: with_status(PermissionUsageStatus::Unused),
+                    override_only: with_status(PermissionUsageStatus::OverrideOnly),
+                    role: role.role,
+                    members: role.members.len(),
+                    permissions,
+                }
+            })
+            .collect();
+
+        Self { tenant_id, since, roles }
+    }
+
+    /// [`Self::to_csv`] below the export header
+    pub fn export_csv(&self, header: &ExportHeader) -> String {
+        let mut csv = header.csv_rows();
+        csv.push_str(&self.to_csv());
+        csv
+    }
+
+    /// One line per role and permission, for the review meeting
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("role,permission,held,status,uses,override_uses,users,last_used\n");
+        for role in &self.roles {
+            for p in &role.permissions {
+                let last_used = p.last_used.map(|d| d.to_string()).unwrap_or_default();
+                let fields = [
+                    csv_field(&role.role),
+                    csv_field(&p.permission),
+                    p.held.to_string(),
+                    p.status.as_str().to_string(),
+                    p.uses.to_string(),
+                    p.override_uses.to_string(),
+                    p.users.to_string(),
+                    last_used,
+                ];
+                csv.push_str(&fields.join(","));
+                csv.push('\n');
+            }
+        }
+        csv
+    }
+}
+
+impl RolePermissionUsage {
+    fn empty(permission: &str, held: bool) -> Self {
+        Self {
+            permission: permission.to_string(),
+            held,
+            status: PermissionUsageStatus::Unused,
+            uses: 0,
+            override_uses: 0,
+            users: 0,
+            last_used: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 10, d).unwrap()
+    }
+
+    fn usage(user_id: Uuid, permission: &str, uses: i64, override_uses: i64, last: u32) -> UserPermissionUsage {
+        UserPermissionUsage {
+            user_id,
+            permission: permission.to_string(),
+            uses,
+            override_uses,
+            last_used: day(last),
+        }
+    }
+
+    #[test]
+    fn test_report_separates_used_unused_and_override_only() {
+        let (alice, bob) = (Uuid::new_v4(), Uuid::new_v4());
+        let roles = vec![RoleGrants {
+            role_id: Uuid::new_v4(),
+            role: "clerk".to_string(),
+            permissions: vec!["products:write".into(), "inventory:write".into(), "customers:delete".into()],
+            members: vec![alice, bob],
+        }];
+        let rows = vec![
+            usage(alice, "products:write", 3, 0, 10),
+            usage(bob, "products:write", 1, 0, 12),
+            usage(bob, "inventory:write", 0, 2, 11),
+            // Through another role
+            usage(alice, "system:admin", 1, 0, 9),
+        ];
+
+        let report = PermissionUsageReport::build(Uuid::new_v4(), day(1), roles, &rows);
+        let clerk = &report.roles[0];
+        assert_eq!(clerk.members, 2);
+        assert_eq!(clerk.unused, vec!["customers:delete".to_string()]);
+        assert_eq!(clerk.override_only, vec!["inventory:write".to_string()]);
+
+        let products = clerk.permissions.iter().find(|p| p.permission == "products:write").unwrap();
+        assert_eq!((products.uses, products.users, products.last_used), (4, 2, Some(day(12))));
+        let admin = clerk.permissions.iter().find(|p| p.permission == "system:admin").unwrap();
+        assert!(!admin.held);
+        assert_eq!(admin.status, PermissionUsageStatus::Used);
+    }
+
+    #[test]
+    fn test_csv_export_quotes_and_neutralizes_fields() {
+        let report = PermissionUsageReport::build(
+            Uuid::new_v4(),
+            day(1),
+            vec![RoleGrants {
+                role_id: Uuid::new_v4(),
+                role: "=HYPERLINK(\"x\"), ops".to_string(),
+                permissions: vec!["products:write".into()],
+                members: vec![],
+            }],
+            &[],
+        );
+        let csv = report.to_csv();
+        let mut lines = csv.lines();
+        assert_eq!(lines.next(), Some("role,permission,held,status,uses,override_uses,users,last_used"));
+        assert_eq!(lines.next(), Some("\"'=HYPERLINK(\"\"x\"\"), ops\",products:write,true,unused,0,0,0,"));
+    }
+
+    #[test]
+    fn test_csv_export_starts_with_the_export_header() {
+        let report = PermissionUsageReport::build(Uuid::new_v4(), day(1), vec![], &[]);
+        let letterhead = crate::export_header::TenantLetterhead {
+            legal_name: "Muster Handel GmbH".to_string(),
+            registration_number: None,
+            vat_id: Some("DE123456789".to_string()),
+            registered_address: None,
+            logo_ref: None,
+        };
+        let header = ExportHeader::new("Permission usage", Some(letterhead), "auditor").with_filter("since", day(1));
+
+        let csv = report.export_csv(&header);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[..3], ["# Muster Handel GmbH", "# VAT id: DE123456789", "# Export: Permission usage"]);
+        assert!(lines[3].starts_with("# Generated at: "));
+        assert_eq!(
+            lines[4..],
+            [
+                "# Requested by: auditor",
+                "# Filters: since=2026-10-01",
+                "role,permission,held,status,uses,override_uses,users,last_used",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_recorder_drops_and_counts_when_channel_is_full() {
+        let (recorder, mut receiver) = PermissionUsageRecorder::new(1, "test").unwrap();
+        let (tenant, user) = (Uuid::new_v4(), Uuid::new_v4());
+        recorder.record(tenant, user, "products:write", false);
+        recorder.record(tenant, user, "products:write", false);
+
+        assert_eq!(recorder.dropped_counter().get(), 1);
+        let recorded = receiver.recv().await.unwrap();
+        assert_eq!(recorded.permission, "products:write");
+        assert!(receiver.try_recv().is_err());
+    }
+}
@@ -0,0 +1,630 @@
+//! # Plan Limits
+//!
+//! Tenants buy plans with data limits. The limits are columns of
+//! `public.tenants`: `customer_limit`, `product_limit` and
+//! `storage_limit_bytes`, next to `seat_limit`, which is the users limit.
+//! NULL means unlimited.
+//!
+//! A nightly job measures every tenant through [`PlanUsageStore`] and stores
+//! the values in `public.tenant_usage_daily`: row counts are planner
+//! estimates, storage is the size of the tenant schema. Each limit is then
+//! checked against the alert thresholds with [`threshold_crossing`], which
+//! notifies a threshold once when it is reached and again only after usage
+//! fell below it in between. [`limit_usage`] turns the current value and the
+//! stored history into what the usage endpoint shows.
+//!
+//! With `enforce_plan_limits` set on the tenant, [`PlanLimitEnforcer`] refuses
+//! new customers and products once the exact count is at the limit, with
+//! [`ErrorCode::PlanLimitExceeded`]. Users are limited by their seats, which
+//! the auth service enforces on its own.
+
+use async_trait::async_trait;
+use chrono::{Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+use sqlx::Row;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::database::DatabasePool;
+use crate::error::{Error, ErrorCode, Result};
+use crate::types::{TenantContext, TenantId};
+
+/// Days of history the trend of a limit looks back
+pub const TREND_DAYS: i64 = 30;
+
+/// What a plan limits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlanResource {
+    Customers,
+    Products,
+    StorageBytes,
+    Users,
+}
+
+impl PlanResource {
+    pub const ALL: [PlanResource; 4] = [
+        PlanResource::Customers,
+        PlanResource::Products,
+        PlanResource::StorageBytes,
+        PlanResource::Users,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PlanResource::Customers => "customers",
+            PlanResource::Products => "products",
+            PlanResource::StorageBytes => "storage_bytes",
+            PlanResource::Users => "users",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "customers" => Some(PlanResource::Customers),
+            "products" => Some(PlanResource::Products),
+            "storage_bytes" => Some(PlanResource::StorageBytes),
+            "users" => Some(PlanResource::Users),
+            _ => None,
+        }
+    }
+
+    /// Name for messages to people
+    pub fn label(self) -> &'static str {
+        match self {
+            PlanResource::Customers => "customers",
+            PlanResource::Products => "products",
+            PlanResource::StorageBytes => "bytes of storage",
+            PlanResource::Users => "users",
+        }
+    }
+}
+
+/// A tenant's plan limits; `None` is unlimited
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct PlanLimits {
+    pub customers: Option<i64>,
+    pub products: Option<i64>,
+    pub storage_bytes: Option<i64>,
+    /// The seat limit
+    pub users: Option<i64>,
+    /// Creating customers and products at their limit is refused
+    pub enforced: bool,
+}
+
+impl PlanLimits {
+    pub fn limit(&self, resource: PlanResource) -> Option<i64> {
+        match resource {
+            PlanResource::Customers => self.customers,
+            PlanResource::Products => self.products,
+            PlanResource::StorageBytes => self.storage_bytes,
+            PlanResource::Users => self.users,
+        }
+    }
+
+    /// Whether reaching the limit of `resource` blocks creating more
+    pub fn enforces(&self, resource: PlanResource) -> bool {
+        match resource {
+            PlanResource::Customers | PlanResource::Products => self.enforced && self.limit(resource).is_some(),
+            PlanResource::Users => self.users.is_some(),
+            PlanResource::StorageBytes => false,
+        }
+    }
+}
+
+/// One nightly measurement
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct UsageMeasurement {
+    pub usage_date: NaiveDate,
+    pub resource: PlanResource,
+    pub value: i64,
+}
+
+/// Share of the limit in use, in percent
+pub fn usage_percent(value: i64, limit: i64) -> f64 {
+    if limit <= 0 {
+        return 100.0;
+    }
+    value as f64 * 100.0 / limit as f64
+}
+
+/// Highest of `thresholds` that `percent` reached
+pub fn threshold_reached(thresholds: &[u32], percent: f64) -> Option<u32> {
+    thresholds.iter().copied().filter(|threshold| percent >= f64::from(*threshold)).max()
+}
+
+/// What a measurement means for the alerts of one limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ThresholdCrossing {
+    /// Threshold to remember as notified
+    pub level: Option<u32>,
+    /// `level` was just crossed upwards and is to be notified
+    pub notify: bool,
+}
+
+/// Compare the threshold `percent` reached with the one last notified
+///
+/// A higher threshold is notified; a lower one is only remembered, so the
+/// higher one is notified again once usage climbs back over it.
+pub fn threshold_crossing(thresholds: &[u32], notified: Option<u32>, percent: f64) -> ThresholdCrossing {
+    let level = threshold_reached(thresholds, percent);
+    ThresholdCrossing {
+        level,
+        notify: level.is_some() && level > notified,
+    }
+}
+
+/// A limit as the usage endpoint shows it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LimitUsage {
+    pub resource: PlanResource,
+    pub limit: Option<i64>,
+    pub current: i64,
+    /// Share of the limit in use, one decimal; `None` without a limit
+    pub percent: Option<f64>,
+    pub enforced: bool,
+    /// Change since the last measurement at least 7 days old
+    pub change_7d: Option<i64>,
+    /// Change since the last measurement at least 30 days old
+    pub change_30d: Option<i64>,
+    /// Days until the limit is reached at the growth of the last 30 days
+    pub days_to_limit: Option<i64>,
+}
+
+/// Current usage of `resource` against its limit, with its trend from `history`
+pub fn limit_usage(
+    resource: PlanResource,
+    limits: &PlanLimits,
+    current: i64,
+    history: &[UsageMeasurement],
+    today: NaiveDate,
+) -> LimitUsage {
+    // Latest measurement of the resource at least `days` before today
+    let measured_before = |days: i64| {
+        history
+            .iter()
+            .filter(|m| m.resource == resource && m.usage_date <= today - Duration::days(days))
+            .max_by_key(|m| m.usage_date)
+    };
+    let limit = limits.limit(resource);
+    let week_ago = measured_before(7);
+    let month_ago = measured_before(TREND_DAYS);
+
+    // Growth per day over the 30 days, or over the longest history there is
+    let baseline = month_ago.or(week_ago);
+    let days_to_limit = match (limit, baseline) {
+        (Some(limit), _) if current >= limit => Some(0),
+        (Some(limit), Some(baseline)) if current > baseline.value => {
+            let days = (today - baseline.usage_date).num_days().max(1) as f64;
+            let per_day = (current - baseline.value) as f64 / days;
+            Some(((limit - current) as f64 / per_day).ceil() as i64)
+        }
+        _ => None,
+    };
+
+    LimitUsage {
+        resource,
+        limit,
+        current,
+        percent: limit.map(|limit| (usage_percent(current, limit) * 10.0).round() / 10.0),
+        enforced: limits.enforces(resource),
+        change_7d: week_ago.map(|m| current - m.value),
+        change_30d: month_ago.map(|m| current - m.value),
+        days_to_limit,
+    }
+}
+
+/// Where plan limits, measurements and notified thresholds are kept
+#[async_trait]
+pub trait PlanUsageStore: Send + Sync {
+    /// Active tenants
+    async fn tenants(&self) -> Result<Vec<Uuid>>;
+
+    async fn limits(&self, tenant_id: Uuid) -> Result<PlanLimits>;
+
+    /// Current value; row counts are planner estimates unless `exact`
+    async fn measure(&self, tenant_id: Uuid, resource: PlanResource, exact: bool) -> Result<i64>;
+
+    /// Store the measurements, replacing ones of the same day
+    async fn record(&self, tenant_id: Uuid, measurements: &[UsageMeasurement]) -> Result<()>;
+
+    /// Measurements since `since`, oldest first
+    async fn history(&self, tenant_id: Uuid, since: NaiveDate) -> Result<Vec<UsageMeasurement>>;
+
+    async fn notified_threshold(&self, tenant_id: Uuid, resource: PlanResource) -> Result<Option<u32>>;
+
+    async fn set_notified_threshold(&self, tenant_id: Uuid, resource: PlanResource, threshold: Option<u32>) -> Result<()>;
+}
+
+/// Refuses creating what the tenant's plan has no room for
+#[async_trait]
+pub trait PlanLimitGuard: Send + Sync {
+    /// Fails with [`ErrorCode::PlanLimitExceeded`] when the tenant enforces the
+    /// limit of `resource` and is at it
+    async fn ensure_room(&self, tenant_id: Uuid, resource: PlanResource) -> Result<()>;
+
+    /// How many more of `resource` the tenant may create; `None` when the limit
+    /// is not enforced or there is none
+    async fn room(&self, tenant_id: Uuid, resource: PlanResource) -> Result<Option<i64>>;
+}
+
+/// [`PlanLimitGuard`] counting exactly through a [`PlanUsageStore`]
+pub struct PlanLimitEnforcer {
+    store: Arc<dyn PlanUsageStore>,
+}
+
+impl PlanLimitEnforcer {
+    pub fn new(store: Arc<dyn PlanUsageStore>) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait]
+impl PlanLimitGuard for PlanLimitEnforcer {
+    async fn ensure_room(&self, tenant_id: Uuid, resource: PlanResource) -> Result<()> {
+        let limits = self.store.limits(tenant_id).await?;
+        if !limits.enforces(resource) {
+            return Ok(());
+        }
+        let Some(limit) = limits.limit(resource) else {
+            return Ok(());
+        };
+        let current = self.store.measure(tenant_id, resource, true).await?;
+        if current >= limit {
+            return Err(Error::new(
+                ErrorCode::PlanLimitExceeded,
+                format!("The plan of this tenant is limited to {} {}", limit, resource.label()),
+            ));
+        }
+        Ok(())
+    }
+
+    async fn room(&self, tenant_id: Uuid, resource: PlanResource) -> Result<Option<i64>> {
+        let limits = self.store.limits(tenant_id).await?;
+        if !limits.enforces(resource) {
+            return Ok(None);
+        }
+        let Some(limit) = limits.limit(resource) else {
+            return Ok(None);
+        };
+        let current = self.store.measure(tenant_id, resource, true).await?;
+        Ok(Some((limit - current).max(0)))
+    }
+}
+
+/// Plan limits in `public.tenants`, usage in `public.tenant_usage_daily`
+pub struct PostgresPlanUsageStore {
+    db: DatabasePool,
+}
+
+impl PostgresPlanUsageStore {
+    pub fn new(db: DatabasePool) -> Self {
+        Self { db }
+    }
+
+    async fn tenant(&self, tenant_id: Uuid) -> Result<TenantContext> {
+        let schema_name: Option<String> =
+            sqlx::query_scalar("SELECT schema_name FROM public.tenants WHERE id = $1")
+                .bind(tenant_id)
+                .fetch_optional(&self.db.main_pool)
+                .await?;
+        let schema_name = schema_name
+            .ok_or_else(|| Error::new(ErrorCode::ResourceNotFound, format!("Tenant {} not found", tenant_id)))?;
+        Ok(TenantContext {
+            tenant_id: TenantId(tenant_id),
+            schema_name,
+        })
+    }
+
+    /// Rows of the tenant matching `filter`, by the planner's estimate or counted
+    async fn count_rows(&self, table: &str, filter: &str, tenant_id: Uuid, exact: bool) -> Result<i64> {
+        if exact {
+            let query = format!("SELECT COUNT(*) FROM {} WHERE tenant_id = $1 {}", table, filter);
+            return Ok(sqlx::query_scalar(&query).bind(tenant_id).fetch_one(&self.db.main_pool).await?);
+        }
+        // EXPLAIN takes no parameters; a formatted UUID is safe to inline
+        let query = format!(
+            "EXPLAIN (FORMAT JSON) SELECT 1 FROM {} WHERE tenant_id = '{}' {}",
+            table, tenant_id, filter
+        );
+        let plan: serde_json::Value = sqlx::query_scalar(&query).fetch_one(&self.db.main_pool).await?;
+        Ok(plan[0]["Plan"]["Plan Rows"].as_f64().unwrap_or(0.0).round() as i64)
+    }
+}
+
+#[async_trait]
+impl PlanUsageStore for PostgresPlanUsageStore {
+    async fn tenants(&self) -> Result<Vec<Uuid>> {
+        Ok(sqlx::query_scalar("SELECT id FROM public.tenants WHERE status = 'active' ORDER BY id")
+            .fetch_all(&self.db.main_pool)
+            .await?)
+    }
+
+    async fn limits(&self, tenant_id: Uuid) -> Result<PlanLimits> {
+        let row = sqlx::query(
+            "SELECT customer_limit, product_limit, storage_limit_bytes, seat_limit, enforce_plan_limits \
+             FROM public.tenants WHERE id = $1",
+        )
+        .bind(tenant_id)
+        .fetch_optional(&self.db.main_pool)
+        .await?;
+        let Some(row) = row else {
+            return Ok(PlanLimits::default());
+        };
+        Ok(PlanLimits {
+            customers: row.try_get("customer_limit")?,
+            products: row.try_get("product_limit")?,
+            storage_bytes: row.try_get("storage_limit_bytes")?,
+            users: row.try_get::<Option<i32>, _>("seat_limit")?.map(i64::from),
+            enforced: row.try_get("enforce_plan_limits")?,
+        })
+    }
+
+    async fn measure(&self, tenant_id: Uuid, resource: PlanResource, exact: bool) -> Result<i64> {
+        match resource {
+            PlanResource::Customers => self.count_rows("customers", "AND is_deleted = false", tenant_id, exact).await,
+            PlanResource::Products => self.count_rows("products", "", tenant_id, exact).await,
+            PlanResource::StorageBytes => {
+                let tenant = self.tenant(tenant_id).await?;
+                Ok(sqlx::query_scalar(
+                    "SELECT COALESCE(SUM(pg_total_relation_size(c.oid)), 0)::BIGINT \
+                     FROM pg_class c JOIN pg_namespace n ON n.oid = c.relnamespace \
+                     WHERE n.nspname = $1 AND c.relkind IN ('r', 'm')",
+                )
+                .bind(&tenant.schema_name)
+                .fetch_one(&self.db.main_pool)
+                .await?)
+            }
+            // Seated users, as the seat limit counts them
+            PlanResource::Users => {
+                let tenant = self.tenant(tenant_id).await?;
+                let pool = self.db.get_tenant_pool(&tenant).await?;
+                Ok(sqlx::query_scalar("SELECT COUNT(*) FROM users WHERE deleted_at IS NULL AND is_active")
+                    .fetch_one(pool.get())
+                    .await?)
+            }
+        }
+    }
+
+    async fn record(&self, tenant_id: Uuid, measurements: &[UsageMeasurement]) -> Result<()> {
+        if measurements.is_empty() {
+            return Ok(());
+        }
+        sqlx::query(
+            "INSERT INTO public.tenant_usage_daily (tenant_id, usage_date, resource, value) \
+             SELECT $1, * FROM UNNEST($2::DATE[], $3::TEXT[], $4::BIGINT[]) \
+             ON CONFLICT (tenant_id, usage_date, resource) DO UPDATE SET \
+                 value = EXCLUDED.value, measured_at = NOW()",
+        )
+        .bind(tenant_id)
+        .bind(measurements.iter().map(|m| m.usage_date).collect::<Vec<_>>())
+        .bind(measurements.iter().map(|m| m.resource.as_str()).collect::<Vec<_>>())
+        .bind(measurements.iter().map(|m| m.value).collect::<Vec<_>>())
+        .execute(&self.db.main_pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn history(&self, tenant_id: Uuid, since: NaiveDate) -> Result<Vec<UsageMeasurement>> {
+        let rows = sqlx::query(
+            "SELECT usage_date, resource, value FROM public.tenant_usage_daily \
+             WHERE tenant_id = $1 AND usage_date >= $2 ORDER BY usage_date",
+        )
+        .bind(tenant_id)
+        .bind(since)
+        .fetch_all(&self.db.main_pool)
+        .await?;
+        let mut history = Vec::with_capacity(rows.len());
+        for row in rows {
+            let resource: String = row.try_get("resource")?;
+            let Some(resource) = PlanResource::parse(&resource) else {
+                continue;
+            };
+            history.push(UsageMeasurement {
+                usage_date: row.try_get("usage_date")?,
+                resource,
+                value: row.try_get("value")?,
+            });
+        }
+        Ok(history)
+    }
+
+    async fn notified_threshold(&self, tenant_id: Uuid, resource: PlanResource) -> Result<Option<u32>> {
+        let percent: Option<i32> = sqlx::query_scalar(
+            "SELECT notified_percent FROM public.tenant_plan_limit_alerts WHERE tenant_id = $1 AND resource = $2",
+        )
+        .bind(tenant_id)
+        .bind(resource.as_str())
+        .fetch_optional(&self.db.main_pool)
+        .await?;
+        Ok(percent.map(|percent| percent.max(0) as u32))
+    }
+
+    async fn set_notified_threshold(&self, tenant_id: Uuid, resource: PlanResource, threshold: Option<u32>) -> Result<()> {
+        match threshold {
+            Some(threshold) => {
+                sqlx::query(
+                    "INSERT INTO public.tenant_plan_limit_alerts (tenant_id, resource, notified_percent) \
+                     VALUES ($1, $2, $3) \
+                     ON CONFLICT (tenant_id, resource) DO UPDATE SET \
+                         notified_percent = EXCLUDED.notified_percent, notified_at = NOW()",
+                )
+                .bind(tenant_id)
+                .bind(resource.as_str())
+                .bind(threshold as i32)
+                .execute(&self.db.main_pool)
+                .await?;
+            }
+            None => {
+                sqlx::query("DELETE FROM public.tenant_plan_limit_alerts WHERE tenant_id = $1 AND resource = $2")
+                    .bind(tenant_id)
+                    .bind(resource.as_str())
+                    .execute(&self.db.main_pool)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    const THRESHOLDS: [u32; 3] = [80, 95, 100];
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 3, day).unwrap()
+    }
+
+    #[test]
+    fn test_threshold_notified_once_per_crossing() {
+        let mut notified = None;
+        let mut notifications = Vec::new();
+        // Nightly percentages: up to 85, stays there, 96, dips below 80, back to 82, then 100
+        for percent in [50.0, 85.0, 86.0, 85.0, 96.0, 97.0, 70.0, 82.0, 82.0, 100.0, 101.0] {
+            let crossing = threshold_crossing(&THRESHOLDS, notified, percent);
+            if crossing.notify {
+                notifications.push(crossing.level.unwrap());
+            }
+            notified = crossing.level;
+        }
+        assert_eq!(notifications, vec![80, 95, 80, 100]);
+    }
+
+    #[test]
+    fn test_limit_usage_math() {
+        let limits = PlanLimits {
+            customers: Some(1000),
+            products: None,
+            enforced: true,
+            ..PlanLimits::default()
+        };
+        let history = vec![
+            UsageMeasurement { usage_date: date(1), resource: PlanResource::Customers, value: 500 },
+            UsageMeasurement { usage_date: date(24), resource: PlanResource::Customers, value: 750 },
+            UsageMeasurement { usage_date: date(25), resource: PlanResource::Customers, value: 760 },
+            UsageMeasurement { usage_date: date(1), resource: PlanResource::Products, value: 10 },
+        ];
+
+        let usage = limit_usage(PlanResource::Customers, &limits, 800, &history, date(31));
+        assert_eq!(usage.limit, Some(1000));
+        assert_eq!(usage.percent, Some(80.0));
+        assert!(usage.enforced);
+        // 7 days before the 31st is the 24th
+        assert_eq!(usage.change_7d, Some(50));
+        assert_eq!(usage.change_30d, Some(300));
+        // 300 more over 30 days is 10 a day, 200 to go
+        assert_eq!(usage.days_to_limit, Some(20));
+
+        let unlimited = limit_usage(PlanResource::Products, &limits, 12, &history, date(31));
+        assert_eq!(unlimited.percent, None);
+        assert_eq!(unlimited.days_to_limit, None);
+        assert!(!unlimited.enforced);
+        assert_eq!(unlimited.change_30d, Some(2));
+
+        // No history yet, and a limit already reached
+        let full = limit_usage(PlanResource::Customers, &limits, 1001, &[], date(31));
+        assert_eq!(full.percent, Some(100.1));
+        assert_eq!(full.change_7d, None);
+        assert_eq!(full.days_to_limit, Some(0));
+    }
+
+    #[derive(Default)]
+    struct MemoryStore {
+        limits: PlanLimits,
+        counts: HashMap<PlanResource, i64>,
+        estimates_used: Mutex<u32>,
+    }
+
+    #[async_trait]
+    impl PlanUsageStore for MemoryStore {
+        async fn tenants(&self) -> Result<Vec<Uuid>> {
+            Ok(vec![Uuid::nil()])
+        }
+
+        async fn limits(&self, _tenant_id: Uuid) -> Result<PlanLimits> {
+            Ok(self.limits.clone())
+        }
+
+        async fn measure(&self, _tenant_id: Uuid, resource: PlanResource, exact: bool) -> Result<i64> {
+            if !exact {
+                *self.estimates_used.lock().unwrap() += 1;
+            }
+            Ok(self.counts.get(&resource).copied().unwrap_or(0))
+        }
+
+        async fn record(&self, _tenant_id: Uuid, _measurements: &[UsageMeasurement]) -> Result<()> {
+            Ok(())
+        }
+
+        async fn history(&self, _tenant_id: Uuid, _since: NaiveDate) -> Result<Vec<UsageMeasurement>> {
+            Ok(Vec::new())
+        }
+
+        async fn notified_threshold(&self, _tenant_id: Uuid, _resource: PlanResource) -> Result<Option<u32>> {
+            Ok(None)
+        }
+
+        async fn set_notified_threshold(&self, _: Uuid, _: PlanResource, _: Option<u32>) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_enforcer_refuses_at_limit_only_when_enforced() {
+        let store = MemoryStore {
+            limits: PlanLimits {
+                customers: Some(100),
+                products: Some(50),
+                enforced: true,
+                ..PlanLimits::default()
+            },
+            counts: HashMap::from([(PlanResource::Customers, 100), (PlanResource::Products, 49)]),
+            ..MemoryStore::default()
+        };
+        let enforcer = PlanLimitEnforcer::new(Arc::new(store));
+
+        let error = enforcer.ensure_room(Uuid::nil(), PlanResource::Customers).await.unwrap_err();
+        assert_eq!(error.code, ErrorCode::PlanLimitExceeded);
+        assert_eq!(error.code.http_status(), 403);
+        assert!(enforcer.ensure_room(Uuid::nil(), PlanResource::Products).await.is_ok());
+        // Storage is never enforced on creation
+        assert!(enforcer.ensure_room(Uuid::nil(), PlanResource::StorageBytes).await.is_ok());
+
+        let soft = PlanLimitEnforcer::new(Arc::new(MemoryStore {
+            limits: PlanLimits { customers: Some(100), ..PlanLimits::default() },
+            counts: HashMap::from([(PlanResource::Customers, 150)]),
+            ..MemoryStore::default()
+        }));
+        assert!(soft.ensure_room(Uuid::nil(), PlanResource::Customers).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_room_left_under_enforced_limits() {
+        let store = MemoryStore {
+            limits: PlanLimits {
+                customers: Some(100),
+                products: Some(50),
+                enforced: true,
+                ..PlanLimits::default()
+            },
+            counts: HashMap::from([(PlanResource::Customers, 120), (PlanResource::Products, 45)]),
+            ..MemoryStore::default()
+        };
+        let enforcer = PlanLimitEnforcer::new(Arc::new(store));
+
+        assert_eq!(enforcer.room(Uuid::nil(), PlanResource::Products).await.unwrap(), Some(5));
+        // Already past the limit, e.g. after it was lowered
+        assert_eq!(enforcer.room(Uuid::nil(), PlanResource::Customers).await.unwrap(), Some(0));
+        assert_eq!(enforcer.room(Uuid::nil(), PlanResource::StorageBytes).await.unwrap(), None);
+
+        let soft = PlanLimitEnforcer::new(Arc::new(MemoryStore {
+            limits: PlanLimits { customers: Some(100), ..PlanLimits::default() },
+            ..MemoryStore::default()
+        }));
+        assert_eq!(soft.room(Uuid::nil(), PlanResource::Customers).await.unwrap(), None);
+    }
+}
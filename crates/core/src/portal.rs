@@ -0,0 +1,197 @@
+//! # Customer Portal Accounts
+//!
+//! A customer's own contacts can be invited to a limited account bound to
+//! that customer. Tokens of such an account carry a [`PortalScope`] claim,
+//! and three rules apply to them regardless of the roles the account holds:
+//!
+//! - their permissions are cut down to the [`PORTAL_ROLE`] template,
+//!   [`PORTAL_PERMISSIONS`], which only holds customer-scoped reads;
+//! - only the routes [`is_portal_route`] allows can be called, so admin and
+//!   user-management endpoints stay closed even if the role is edited;
+//! - every customer read is filtered to the [`CustomerVisibility`] of the
+//!   scope: the bound customer, and with `include_children` its hierarchy
+//!   below it.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+/// Role template assigned to portal accounts
+pub const PORTAL_ROLE: &str = "customer_portal";
+
+/// Permissions of the portal role template; a portal token never holds others
+pub const PORTAL_PERMISSIONS: &[&str] = &["customers:read"];
+
+/// Routes a portal token may call, relative to the versioned API root
+const PORTAL_ROUTES: &[(&str, &str)] = &[
+    ("GET", "/customers"),
+    ("GET", "/customers/:id"),
+    ("GET", "/customers/:id/hierarchy"),
+    ("POST", "/auth/refresh"),
+    ("POST", "/auth/logout"),
+    ("POST", "/password"),
+];
+
+/// The customer a portal account is bound to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PortalScope {
+    pub customer_id: Uuid,
+    /// Also see the customers below `customer_id` in the hierarchy
+    #[serde(default)]
+    pub include_children: bool,
+}
+
+/// The portal template's share of `permissions`; role edits can narrow a
+/// portal account but never widen it
+pub fn portal_permissions<S: AsRef<str>>(permissions: &[S]) -> Vec<String> {
+    permissions
+        .iter()
+        .map(|p| p.as_ref())
+        .filter(|p| PORTAL_PERMISSIONS.contains(p))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Whether a portal token may call `method` on `path`; a leading `/api/vN`
+/// is ignored
+pub fn is_portal_route(method: &str, path: &str) -> bool {
+    let path = strip_api_version(path.trim_end_matches('/'));
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    PORTAL_ROUTES.iter().any(|(route_method, route)| {
+        let pattern: Vec<&str> = route.split('/').filter(|s| !s.is_empty()).collect();
+        route_method.eq_ignore_ascii_case(method)
+            && pattern.len() == segments.len()
+            && pattern
+                .iter()
+                .zip(&segments)
+                .all(|(expected, actual)| expected.starts_with(':') || expected == actual)
+    })
+}
+
+fn strip_api_version(path: &str) -> &str {
+    let Some(rest) = path.strip_prefix("/api/v") else {
+        return path;
+    };
+    let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+    if digits == 0 {
+        return path;
+    }
+    &rest[digits..]
+}
+
+/// The customers a portal scope can see
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CustomerVisibility {
+    visible: HashSet<Uuid>,
+}
+
+impl CustomerVisibility {
+    /// Resolve a scope against the `(customer, parent)` pairs of its
+    /// hierarchy; without `include_children` the hierarchy is ignored
+    pub fn resolve(scope: &PortalScope, hierarchy: &[(Uuid, Option<Uuid>)]) -> Self {
+        let mut visible = HashSet::from([scope.customer_id]);
+        if scope.include_children {
+            let mut children: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+            for (id, parent) in hierarchy {
+                if let Some(parent) = parent {
+                    children.entry(*parent).or_default().push(*id);
+                }
+            }
+            let mut pending = vec![scope.customer_id];
+            while let Some(id) = pending.pop() {
+                for child in children.get(&id).into_iter().flatten() {
+                    if visible.insert(*child) {
+                        pending.push(*child);
+                    }
+                }
+            }
+        }
+        Self { visible }
+    }
+
+    pub fn allows(&self, customer_id: Uuid) -> bool {
+        self.visible.contains(&customer_id)
+    }
+
+    /// Visible customers, sorted for stable paging
+    pub fn customer_ids(&self) -> Vec<Uuid> {
+        let mut ids: Vec<Uuid> = self.visible.iter().copied().collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    /// Keep the items of visible customers
+    pub fn retain<T>(&self, items: &mut Vec<T>, customer_id: impl Fn(&T) -> Uuid) {
+        items.retain(|item| self.allows(customer_id(item)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scope(customer_id: Uuid, include_children: bool) -> PortalScope {
+        PortalScope {
+            customer_id,
+            include_children,
+        }
+    }
+
+    #[test]
+    fn test_hierarchy_toggle() {
+        let (parent, own, child, grandchild, sibling) =
+            (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let hierarchy = vec![
+            (own, Some(parent)),
+            (child, Some(own)),
+            (grandchild, Some(child)),
+            (sibling, Some(parent)),
+        ];
+
+        let alone = CustomerVisibility::resolve(&scope(own, false), &hierarchy);
+        assert_eq!(alone.customer_ids(), vec![own]);
+        assert!(!alone.allows(child));
+
+        let with_children = CustomerVisibility::resolve(&scope(own, true), &hierarchy);
+        assert!(with_children.allows(own) && with_children.allows(child) && with_children.allows(grandchild));
+        // Never up or sideways
+        assert!(!with_children.allows(parent));
+        assert!(!with_children.allows(sibling));
+    }
+
+    #[test]
+    fn test_list_and_direct_access_are_scoped() {
+        let (own, other) = (Uuid::new_v4(), Uuid::new_v4());
+        let visibility = CustomerVisibility::resolve(&scope(own, true), &[]);
+
+        let mut listed = vec![(other, "Other GmbH"), (own, "Own AG")];
+        visibility.retain(&mut listed, |(id, _)| *id);
+        assert_eq!(listed, vec![(own, "Own AG")]);
+
+        assert!(visibility.allows(own));
+        assert!(!visibility.allows(other));
+    }
+
+    #[test]
+    fn test_permissions_are_cut_to_the_template() {
+        let granted = ["customers:read", "customers:write", "system:admin", "users:read"];
+        assert_eq!(portal_permissions(&granted), vec!["customers:read".to_string()]);
+        assert!(portal_permissions::<&str>(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_portal_routes() {
+        assert!(is_portal_route("GET", "/api/v1/customers"));
+        assert!(is_portal_route("get", "/customers/6f1c1c52-52e4-4d0c-a0f3-1d4b8f4d9a10/"));
+        assert!(is_portal_route("GET", "/api/v2/customers/abc/hierarchy"));
+        assert!(is_portal_route("POST", "/api/v1/auth/logout"));
+
+        assert!(!is_portal_route("PUT", "/api/v1/customers/abc"));
+        assert!(!is_portal_route("GET", "/api/v1/customers/abc/financial-identifiers"));
+        assert!(!is_portal_route("GET", "/api/v1/customers/abc/portal-users"));
+        assert!(!is_portal_route("GET", "/api/v1/users"));
+        assert!(!is_portal_route("POST", "/api/v1/roles"));
+        assert!(!is_portal_route("GET", "/api/v1/admin/tenants"));
+        assert!(!is_portal_route("GET", "/api/vx/customers"));
+    }
+}
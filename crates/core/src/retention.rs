@@ -0,0 +1,302 @@
+//! # Data Retention
+//!
+//! Per-tenant retention policies enforced by one nightly job.
+//!
+//! Modules that keep time-bound data register a [`RetentionCategory`] with
+//! the [`RetentionRegistry`]: a name, a built-in default period and action,
+//! and a [`RetentionHandler`] that counts and removes expired rows. Policies
+//! stored in `retention_policies` override the default, first per tenant and
+//! then globally (rows without a tenant). Policies are checked against the
+//! legal minimums in `[retention]` before they are saved.
+
+use crate::error::{Error, ErrorCode, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// What happens to rows past their retention period
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RetentionAction {
+    Delete,
+    /// Keep the row but strip personal data
+    Anonymize,
+    /// Move the row to cold storage
+    Archive,
+}
+
+impl RetentionAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RetentionAction::Delete => "delete",
+            RetentionAction::Anonymize => "anonymize",
+            RetentionAction::Archive => "archive",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "delete" => Some(RetentionAction::Delete),
+            "anonymize" => Some(RetentionAction::Anonymize),
+            "archive" => Some(RetentionAction::Archive),
+            _ => None,
+        }
+    }
+}
+
+/// A stored policy; `tenant_id: None` is the global default for the category
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    pub tenant_id: Option<Uuid>,
+    pub category: String,
+    pub retention_days: i64,
+    pub action: RetentionAction,
+    pub updated_by: Option<Uuid>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Where an effective policy came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PolicySource {
+    Tenant,
+    Global,
+    /// The default the category was registered with
+    Builtin,
+}
+
+/// The policy that applies to one tenant and category
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EffectivePolicy {
+    pub tenant_id: Uuid,
+    pub category: String,
+    pub retention_days: i64,
+    pub action: RetentionAction,
+    pub source: PolicySource,
+}
+
+impl EffectivePolicy {
+    /// Rows older than this are expired
+    pub fn cutoff(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        now - Duration::days(self.retention_days)
+    }
+}
+
+/// Counts and removes expired rows of one category
+#[async_trait]
+pub trait RetentionHandler: Send + Sync {
+    /// Rows of the tenant recorded before `cutoff` that `action` would still touch
+    async fn count_expired(&self, tenant_id: Uuid, cutoff: DateTime<Utc>, action: RetentionAction) -> Result<u64>;
+
+    /// Apply `action` to at most `limit` expired rows; returns rows affected
+    async fn enforce_batch(
+        &self,
+        tenant_id: Uuid,
+        cutoff: DateTime<Utc>,
+        action: RetentionAction,
+        limit: i64,
+    ) -> Result<u64>;
+}
+
+/// A kind of data with a retention period, declared by the module that owns it
+#[derive(Clone)]
+pub struct RetentionCategory {
+    pub name: String,
+    pub description: String,
+    pub default_days: i64,
+    pub default_action: RetentionAction,
+    /// Actions the handler implements
+    pub supported_actions: Vec<RetentionAction>,
+    pub handler: Arc<dyn RetentionHandler>,
+}
+
+impl std::fmt::Debug for RetentionCategory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RetentionCategory")
+            .field("name", &self.name)
+            .field("default_days", &self.default_days)
+            .field("default_action", &self.default_action)
+            .field("supported_actions", &self.supported_actions)
+            .finish()
+    }
+}
+
+/// All categories known to the enforcement job
+#[derive(Debug, Clone, Default)]
+pub struct RetentionRegistry {
+    categories: BTreeMap<String, RetentionCategory>,
+}
+
+impl RetentionRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(&mut self, category: RetentionCategory) -> &mut Self {
+        self.categories.insert(category.name.clone(), category);
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&RetentionCategory> {
+        self.categories.get(name)
+    }
+
+    pub fn categories(&self) -> impl Iterator<Item = &RetentionCategory> {
+        self.categories.values()
+    }
+
+    /// Effective policy of every category for a tenant; `policies` may mix tenant and global rows
+    pub fn resolve(&self, tenant_id: Uuid, policies: &[RetentionPolicy]) -> Vec<EffectivePolicy> {
+        self.categories
+            .values()
+            .map(|category| resolve_policy(category, tenant_id, policies))
+            .collect()
+    }
+}
+
+/// Tenant policy, else global policy, else the category default
+pub fn resolve_policy(category: &RetentionCategory, tenant_id: Uuid, policies: &[RetentionPolicy]) -> EffectivePolicy {
+    let matching = |tenant: Option<Uuid>| {
+        policies
+            .iter()
+            .find(|p| p.category == category.name && p.tenant_id == tenant)
+    };
+
+    let (retention_days, action, source) = if let Some(policy) = matching(Some(tenant_id)) {
+        (policy.retention_days, policy.action, PolicySource::Tenant)
+    } else if let Some(policy) = matching(None) {
+        (policy.retention_days, policy.action, PolicySource::Global)
+    } else {
+        (category.default_days, category.default_action, PolicySource::Builtin)
+    };
+
+    EffectivePolicy {
+        tenant_id,
+        category: category.name.clone(),
+        retention_days,
+        action,
+        source,
+    }
+}
+
+/// Reject policies for unknown categories, unsupported actions or periods below the legal minimum
+pub fn validate_policy(
+    registry: &RetentionRegistry,
+    policy: &RetentionPolicy,
+    legal_minimum_days: &HashMap<String, i64>,
+) -> Result<()> {
+    let category = registry.get(&policy.category).ok_or_else(|| {
+        Error::new(
+            ErrorCode::ValidationFailed,
+            format!("Unknown retention category '{}'", policy.category),
+        )
+    })?;
+
+    if !category.supported_actions.contains(&policy.action) {
+        return Err(Error::new(
+            ErrorCode::ValidationFailed,
+            format!(
+                "Category '{}' does not support the '{}' action",
+                category.name,
+                policy.action.as_str()
+            ),
+        ));
+    }
+
+    let minimum = legal_minimum_days.get(&policy.category).copied().unwrap_or(1).max(1);
+    if policy.retention_days < minimum {
+        return Err(Error::new(
+            ErrorCode::ValidationFailed,
+            format!(
+                "Retention for '{}' must be at least {} days, got {}",
+                category.name, minimum, policy.retention_days
+            ),
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct NoopHandler;
+
+    #[async_trait]
+    impl RetentionHandler for NoopHandler {
+        async fn count_expired(&self, _: Uuid, _: DateTime<Utc>, _: RetentionAction) -> Result<u64> {
+            Ok(0)
+        }
+
+        async fn enforce_batch(&self, _: Uuid, _: DateTime<Utc>, _: RetentionAction, _: i64) -> Result<u64> {
+            Ok(0)
+        }
+    }
+
+    fn registry() -> RetentionRegistry {
+        let mut registry = RetentionRegistry::new();
+        registry.register(RetentionCategory {
+            name: "customer_communications".to_string(),
+            description: "Customer communication log".to_string(),
+            default_days: 1095,
+            default_action: RetentionAction::Delete,
+            supported_actions: vec![RetentionAction::Delete, RetentionAction::Anonymize],
+            handler: Arc::new(NoopHandler),
+        });
+        registry
+    }
+
+    fn policy(tenant_id: Option<Uuid>, days: i64, action: RetentionAction) -> RetentionPolicy {
+        RetentionPolicy {
+            tenant_id,
+            category: "customer_communications".to_string(),
+            retention_days: days,
+            action,
+            updated_by: None,
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_tenant_override_beats_global_default() {
+        let registry = registry();
+        let (german, other) = (Uuid::new_v4(), Uuid::new_v4());
+
+        let builtin = &registry.resolve(other, &[])[0];
+        assert_eq!((builtin.retention_days, builtin.source), (1095, PolicySource::Builtin));
+
+        let policies = vec![
+            policy(None, 730, RetentionAction::Delete),
+            policy(Some(german), 365, RetentionAction::Anonymize),
+        ];
+        let global = &registry.resolve(other, &policies)[0];
+        assert_eq!((global.retention_days, global.source), (730, PolicySource::Global));
+
+        let overridden = &registry.resolve(german, &policies)[0];
+        assert_eq!(overridden.retention_days, 365);
+        assert_eq!(overridden.action, RetentionAction::Anonymize);
+        assert_eq!(overridden.source, PolicySource::Tenant);
+    }
+
+    #[test]
+    fn test_validation_against_legal_minimums() {
+        let registry = registry();
+        let minimums = HashMap::from([("customer_communications".to_string(), 365)]);
+
+        assert!(validate_policy(&registry, &policy(None, 365, RetentionAction::Delete), &minimums).is_ok());
+
+        let too_short = validate_policy(&registry, &policy(None, 30, RetentionAction::Delete), &minimums).unwrap_err();
+        assert!(too_short.message.contains("at least 365 days"));
+
+        let unsupported = validate_policy(&registry, &policy(None, 400, RetentionAction::Archive), &minimums);
+        assert!(unsupported.is_err());
+
+        let mut unknown = policy(None, 400, RetentionAction::Delete);
+        unknown.category = "api_request_logs".to_string();
+        assert!(validate_policy(&registry, &unknown, &minimums).is_err());
+    }
+}
@@ -5,5 +5,8 @@ pub mod totp;
 
 pub use encryption::EncryptionService;
 pub use hashing::PasswordHasher;
-pub use jwt::{JwtService, TokenPair};
+pub use jwt::{
+    is_past_grace, is_token_superseded, revoked_session_key, tenant_tokens_valid_after_key, token_cutoff_keys, token_revoked_after_key,
+    tokens_valid_after_key, JwtService, TokenExpiryHint, TokenPair,
+};
 pub use totp::TotpService;
\ No newline at end of file
@@ -1,4 +1,10 @@
-use crate::{config::JwtConfig, error::Result, types::JwtClaims, Error};
+use crate::{
+    config::{JwtConfig, TokenRefreshConfig},
+    error::Result,
+    portal::{portal_permissions, PortalScope, PORTAL_ROLE},
+    types::JwtClaims,
+    Error,
+};
 use chrono::{Duration, Utc};
 use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
 use serde::{Deserialize, Serialize};
@@ -18,6 +24,10 @@ pub struct RefreshTokenClaims {
     pub iat: i64,
     pub jti: String,
     pub token_version: u32,
+    /// Session the token was issued for, handed on to the tokens it is
+    /// exchanged for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -32,12 +42,23 @@ pub struct SessionTokenClaims {
     pub purpose: String,
 }
 
+/// How much longer an access token is accepted, for the response headers
+/// of authenticated requests
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TokenExpiryHint {
+    /// Seconds until the token expires, never negative
+    pub expires_in: i64,
+    /// Whether the token has used up enough of its lifetime to be refreshed
+    pub refresh_advised: bool,
+}
+
 #[derive(Clone)]
 pub struct JwtService {
     encoding_key: EncodingKey,
     decoding_key: DecodingKey,
     access_token_expiry: Duration,
     refresh_token_expiry: Duration,
+    token_refresh: TokenRefreshConfig,
 }
 
 impl JwtService {
@@ -50,9 +71,58 @@ impl JwtService {
             decoding_key,
             access_token_expiry: Duration::seconds(config.access_token_expiry),
             refresh_token_expiry: Duration::seconds(config.refresh_token_expiry),
+            token_refresh: TokenRefreshConfig::default(),
         })
     }
 
+    /// When responses advise a refresh, and how long a token replaced by a
+    /// sliding session refresh stays valid
+    pub fn with_token_refresh(mut self, config: &TokenRefreshConfig) -> Self {
+        self.token_refresh = config.clone();
+        self
+    }
+
+    /// Seconds a token replaced by a sliding session refresh stays valid
+    pub fn sliding_grace_seconds(&self) -> i64 {
+        self.token_refresh.sliding_grace_seconds
+    }
+
+    /// Remaining lifetime of an access token at `now` (Unix seconds). A
+    /// refresh is advised once at most `refresh_advised_fraction` of the
+    /// lifetime it was issued with is left.
+    pub fn expiry_hint(&self, claims: &JwtClaims, now: i64) -> TokenExpiryHint {
+        let lifetime = (claims.exp - claims.iat).max(0);
+        let expires_in = (claims.exp - now).max(0);
+        TokenExpiryHint {
+            expires_in,
+            refresh_advised: (expires_in as f64) <= lifetime as f64 * self.token_refresh.refresh_advised_fraction,
+        }
+    }
+
+    /// A fresh access token with the same grants as `claims`, for sliding
+    /// sessions. The new token gets its own `jti` and a full lifetime.
+    /// Impersonation tokens are never extended this way; the administrator
+    /// has to start a new impersonation session.
+    pub fn refresh_access_token(&self, claims: &JwtClaims) -> Result<String> {
+        if claims.impersonator_id.is_some() {
+            return Err(Error::new(
+                crate::error::ErrorCode::PermissionDenied,
+                "Impersonation tokens cannot be refreshed",
+            ));
+        }
+
+        let now = Utc::now();
+        let access_claims = JwtClaims {
+            exp: (now + self.access_token_expiry).timestamp(),
+            iat: now.timestamp(),
+            jti: Uuid::new_v4().to_string(),
+            ..claims.clone()
+        };
+
+        encode(&Header::new(Algorithm::HS512), &access_claims, &self.encoding_key)
+            .map_err(|e| Error::new(crate::error::ErrorCode::TokenInvalid, format!("Failed to generate access token: {}", e)))
+    }
+
     pub fn generate_token_pair(
         &self,
         user_id: &str,
@@ -60,6 +130,54 @@ impl JwtService {
         roles: Vec<String>,
         permissions: Vec<String>,
         impersonator_id: Option<String>,
+    ) -> Result<TokenPair> {
+        self.issue_token_pair(user_id, tenant_id, roles, permissions, impersonator_id, None, None)
+    }
+
+    /// Tokens bound to the login session `session_id`, so the session they
+    /// were issued for can be told apart from the user's other sessions
+    pub fn generate_session_token_pair(
+        &self,
+        user_id: &str,
+        tenant_id: &str,
+        roles: Vec<String>,
+        permissions: Vec<String>,
+        session_id: Option<&str>,
+    ) -> Result<TokenPair> {
+        self.issue_token_pair(user_id, tenant_id, roles, permissions, None, None, session_id.map(str::to_string))
+    }
+
+    /// Tokens of a customer portal account: the portal role, the template's
+    /// share of `permissions` and the scope claim
+    pub fn generate_portal_token_pair(
+        &self,
+        user_id: &str,
+        tenant_id: &str,
+        permissions: &[String],
+        scope: PortalScope,
+        session_id: Option<&str>,
+    ) -> Result<TokenPair> {
+        self.issue_token_pair(
+            user_id,
+            tenant_id,
+            vec![PORTAL_ROLE.to_string()],
+            portal_permissions(permissions),
+            None,
+            Some(scope),
+            session_id.map(str::to_string),
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn issue_token_pair(
+        &self,
+        user_id: &str,
+        tenant_id: &str,
+        roles: Vec<String>,
+        permissions: Vec<String>,
+        impersonator_id: Option<String>,
+        portal: Option<PortalScope>,
+        session_id: Option<String>,
     ) -> Result<TokenPair> {
         let now = Utc::now();
         let access_jti = Uuid::new_v4().to_string();
@@ -74,6 +192,8 @@ impl JwtService {
             iat: now.timestamp(),
             jti: access_jti,
             impersonator_id,
+            portal,
+            sid: session_id.clone(),
         };
 
         let refresh_claims = RefreshTokenClaims {
@@ -83,6 +203,7 @@ impl JwtService {
             iat: now.timestamp(),
             jti: refresh_jti,
             token_version: 1,
+            sid: session_id,
         };
 
         let header = Header::new(Algorithm::HS512);
@@ -154,4 +275,147 @@ impl JwtService {
 
         Ok(token_data.claims)
     }
-}
\ No newline at end of file
+}
+
+/// Redis key holding the Unix time before which a user's tokens are no longer
+/// accepted. Set when the password changes so every token issued earlier,
+/// access and refresh alike, stops working without tracking each `jti`.
+pub fn tokens_valid_after_key(tenant_id: &str, user_id: &str) -> String {
+    format!("tokens_valid_after:{}:{}", tenant_id, user_id)
+}
+
+/// Redis key holding the Unix time before which no token issued for the
+/// tenant is accepted, whoever it belongs to. Set when a sandbox tenant is
+/// reset to its snapshot.
+pub fn tenant_tokens_valid_after_key(tenant_id: &str) -> String {
+    format!("tenant_tokens_valid_after:{}", tenant_id)
+}
+
+/// Both cutoffs that apply to a user's tokens; the later one counts
+pub fn token_cutoff_keys(tenant_id: &str, user_id: &str) -> [String; 2] {
+    [tokens_valid_after_key(tenant_id, user_id), tenant_tokens_valid_after_key(tenant_id)]
+}
+
+/// Whether a token issued at `issued_at` predates the user's cutoff
+pub fn is_token_superseded(issued_at: i64, valid_after: Option<i64>) -> bool {
+    valid_after.is_some_and(|cutoff| issued_at < cutoff)
+}
+
+/// Redis key holding the Unix time after which the access token `jti` is no
+/// longer accepted. Set when a sliding session refresh replaces the token, so
+/// requests already sent with it still pass for a short grace period.
+pub fn token_revoked_after_key(jti: &str) -> String {
+    format!("token_revoked_after:{}", jti)
+}
+
+/// Redis key marking the login session `session_id` as signed out. Every
+/// token bound to the session, access and refresh alike, is refused from then
+/// on, including those of other devices that never saw the sign-out.
+pub fn revoked_session_key(session_id: &str) -> String {
+    format!("revoked_session:{}", session_id)
+}
+
+/// Whether a token replaced at `revoked_after` has run out of its grace period
+pub fn is_past_grace(now: i64, revoked_after: Option<i64>) -> bool {
+    revoked_after.is_some_and(|cutoff| now >= cutoff)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn service(fraction: f64) -> JwtService {
+        let config = JwtConfig {
+            secret: "test-secret-with-enough-length-for-hs512".to_string(),
+            access_token_expiry: 900,
+            refresh_token_expiry: 86400,
+        };
+        JwtService::new(&config).unwrap().with_token_refresh(&TokenRefreshConfig {
+            refresh_advised_fraction: fraction,
+            sliding_grace_seconds: 30,
+        })
+    }
+
+    fn claims(iat: i64, exp: i64, impersonator_id: Option<String>) -> JwtClaims {
+        JwtClaims {
+            sub: Uuid::new_v4().to_string(),
+            tenant_id: Uuid::new_v4().to_string(),
+            roles: vec!["clerk".to_string()],
+            permissions: vec!["products:read".to_string()],
+            exp,
+            iat,
+            jti: Uuid::new_v4().to_string(),
+            impersonator_id,
+            portal: None,
+            sid: None,
+        }
+    }
+
+    #[test]
+    fn test_refresh_is_advised_in_the_last_fraction_of_the_lifetime() {
+        let jwt = service(0.2);
+        let token = claims(1_000, 1_900, None);
+
+        let hint = |now| jwt.expiry_hint(&token, now);
+        assert_eq!(hint(1_000), TokenExpiryHint { expires_in: 900, refresh_advised: false });
+        assert_eq!(hint(1_719), TokenExpiryHint { expires_in: 181, refresh_advised: false });
+        assert_eq!(hint(1_720), TokenExpiryHint { expires_in: 180, refresh_advised: true });
+        assert_eq!(hint(1_899), TokenExpiryHint { expires_in: 1, refresh_advised: true });
+        // Clock skew past expiry never reports a negative lifetime
+        assert_eq!(hint(2_000), TokenExpiryHint { expires_in: 0, refresh_advised: true });
+
+        let late = service(0.5);
+        assert!(late.expiry_hint(&token, 1_450).refresh_advised);
+        assert!(!late.expiry_hint(&token, 1_449).refresh_advised);
+    }
+
+    #[test]
+    fn test_refreshed_token_keeps_the_grants_with_a_new_jti_and_lifetime() {
+        let jwt = service(0.2);
+        let now = Utc::now().timestamp();
+        let old = claims(now - 800, now + 100, None);
+
+        let refreshed = jwt.verify_access_token(&jwt.refresh_access_token(&old).unwrap()).unwrap();
+        assert_ne!(refreshed.jti, old.jti);
+        assert_eq!((&refreshed.sub, &refreshed.tenant_id), (&old.sub, &old.tenant_id));
+        assert_eq!((&refreshed.roles, &refreshed.permissions), (&old.roles, &old.permissions));
+        assert!(refreshed.iat >= now);
+        assert_eq!(refreshed.exp - refreshed.iat, 900);
+        assert!(!jwt.expiry_hint(&refreshed, now).refresh_advised);
+    }
+
+    #[test]
+    fn test_session_tokens_carry_their_session_through_refreshes() {
+        let jwt = service(0.2);
+        let pair = jwt
+            .generate_session_token_pair("user", "tenant", vec![], vec![], Some("session-1"))
+            .unwrap();
+
+        let access = jwt.verify_access_token(&pair.access_token).unwrap();
+        assert_eq!(access.sid.as_deref(), Some("session-1"));
+        assert_eq!(jwt.verify_refresh_token(&pair.refresh_token).unwrap().sid.as_deref(), Some("session-1"));
+        let refreshed = jwt.verify_access_token(&jwt.refresh_access_token(&access).unwrap()).unwrap();
+        assert_eq!(refreshed.sid.as_deref(), Some("session-1"));
+
+        let unbound = jwt.generate_token_pair("user", "tenant", vec![], vec![], None).unwrap();
+        assert_eq!(jwt.verify_access_token(&unbound.access_token).unwrap().sid, None);
+    }
+
+    #[test]
+    fn test_impersonation_tokens_are_never_refreshed() {
+        let jwt = service(0.2);
+        let now = Utc::now().timestamp();
+        let impersonated = claims(now - 800, now + 100, Some(Uuid::new_v4().to_string()));
+
+        let err = jwt.refresh_access_token(&impersonated).unwrap_err();
+        assert_eq!(err.code, crate::error::ErrorCode::PermissionDenied);
+    }
+
+    #[test]
+    fn test_replaced_token_passes_until_its_grace_period_ends() {
+        assert!(!is_past_grace(1_000, None));
+        assert!(!is_past_grace(1_029, Some(1_030)));
+        assert!(is_past_grace(1_030, Some(1_030)));
+        assert_eq!(token_revoked_after_key("abc"), "token_revoked_after:abc");
+    }
+}
@@ -57,6 +57,114 @@ pub enum SessionState {
     Suspended,
 }
 
+/// What happens when a user who already holds the maximum number of sessions signs in again
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionLimitStrategy {
+    /// Sign out the user's oldest sessions to make room
+    #[default]
+    EvictOldest,
+    /// Refuse the new session
+    Reject,
+}
+
+impl SessionLimitStrategy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SessionLimitStrategy::EvictOldest => "evict_oldest",
+            SessionLimitStrategy::Reject => "reject",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "evict_oldest" => Some(SessionLimitStrategy::EvictOldest),
+            "reject" => Some(SessionLimitStrategy::Reject),
+            _ => None,
+        }
+    }
+}
+
+/// Cap on the concurrent sessions of one user
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionLimit {
+    pub max_sessions_per_user: u32,
+    pub strategy: SessionLimitStrategy,
+    /// Whether requests near the end of an access token's lifetime get a
+    /// fresh token, so active users are never signed out
+    #[serde(default)]
+    pub sliding_sessions: bool,
+}
+
+/// A new session, and the sessions signed out to make room for it
+#[derive(Debug, Clone)]
+pub struct CreatedSession {
+    pub session: SessionData,
+    /// Active sessions of the user, the new one included
+    pub active_sessions: u32,
+    pub evicted: Vec<SessionData>,
+}
+
+/// Checks the user's sessions against the cap and stores the new one in one
+/// step, so concurrent logins cannot overshoot the cap. Index entries whose
+/// session is gone are dropped on the way. Sessions are ordered by their
+/// RFC 3339 `created_at` with the fraction padded to nanoseconds, so that it
+/// sorts like the time it denotes.
+///
+/// KEYS: the user's session index, the new session.
+/// ARGV: session key prefix, new session id, new session JSON, session TTL,
+/// cap, strategy, index TTL.
+/// Returns the outcome (1 created, 0 rejected), the user's active sessions
+/// and the JSON of every evicted session.
+const CREATE_SESSION_SCRIPT: &str = r#"
+local function instant(created)
+    local seconds, fraction = string.match(created, '^([^.Z+]+)%.?(%d*)')
+    if not seconds then
+        return created
+    end
+    return seconds .. string.sub(fraction .. '000000000', 1, 9)
+end
+
+local live = {}
+for _, id in ipairs(redis.call('SMEMBERS', KEYS[1])) do
+    local data = redis.call('GET', ARGV[1] .. id)
+    if data then
+        local ok, session = pcall(cjson.decode, data)
+        local created = ''
+        if ok and type(session.created_at) == 'string' then
+            created = instant(session.created_at)
+        end
+        table.insert(live, { id = id, created = created, data = data })
+    else
+        redis.call('SREM', KEYS[1], id)
+    end
+end
+
+local max = tonumber(ARGV[5])
+local evicted = {}
+if #live >= max then
+    if ARGV[6] == 'reject' then
+        return { '0', tostring(#live) }
+    end
+    table.sort(live, function(a, b) return a.created < b.created end)
+    for i = 1, #live - max + 1 do
+        redis.call('DEL', ARGV[1] .. live[i].id)
+        redis.call('SREM', KEYS[1], live[i].id)
+        table.insert(evicted, live[i].data)
+    end
+end
+
+redis.call('SET', KEYS[2], ARGV[3], 'EX', ARGV[4])
+redis.call('SADD', KEYS[1], ARGV[2])
+redis.call('EXPIRE', KEYS[1], ARGV[7])
+
+local result = { '1', tostring(#live - #evicted + 1) }
+for _, data in ipairs(evicted) do
+    table.insert(result, data)
+end
+return result
+"#;
+
 /// Session configuration
 #[derive(Debug, Clone)]
 pub struct SessionConfig {
@@ -68,6 +176,8 @@ pub struct SessionConfig {
     pub cleanup_interval: Duration,
     /// Maximum sessions per user (default: 10)
     pub max_sessions_per_user: u32,
+    /// What a login beyond `max_sessions_per_user` does (default: evict the oldest session)
+    pub session_limit_strategy: SessionLimitStrategy,
     /// Enable sliding window timeout
     pub enable_sliding_window: bool,
     /// Require device consistency
@@ -81,12 +191,24 @@ impl Default for SessionConfig {
             absolute_timeout: Duration::hours(12),
             cleanup_interval: Duration::minutes(5),
             max_sessions_per_user: 10,
+            session_limit_strategy: SessionLimitStrategy::EvictOldest,
             enable_sliding_window: true,
             require_device_consistency: false,
         }
     }
 }
 
+impl SessionConfig {
+    /// The cap applied to tenants without one of their own
+    pub fn session_limit(&self) -> SessionLimit {
+        SessionLimit {
+            max_sessions_per_user: self.max_sessions_per_user,
+            strategy: self.session_limit_strategy,
+            sliding_sessions: false,
+        }
+    }
+}
+
 /// Session manager for handling user sessions with Redis storage
 pub struct SessionManager {
     redis: ConnectionManager,
@@ -99,7 +221,12 @@ impl SessionManager {
         Self { redis, config }
     }
 
-    /// Create a new session for a user
+    /// The session limit of tenants without one of their own
+    pub fn default_limit(&self) -> SessionLimit {
+        self.config.session_limit()
+    }
+
+    /// Create a new session for a user, capped by the configured session limit
     pub async fn create_session(
         &self,
         tenant: &TenantContext,
@@ -108,11 +235,31 @@ impl SessionManager {
         user_agent: Option<String>,
         device_fingerprint: Option<String>,
     ) -> Result<SessionData> {
+        let created = self
+            .create_session_within(tenant, user_id, client_ip, user_agent, device_fingerprint, self.config.session_limit())
+            .await?;
+        Ok(created.session)
+    }
+
+    /// Create a new session for a user who may hold at most `limit` sessions.
+    ///
+    /// At the cap, the oldest sessions are signed out or the new session is
+    /// refused with `ConcurrencyLimitExceeded`, depending on the strategy.
+    /// The check and the creation are one atomic step in Redis.
+    pub async fn create_session_within(
+        &self,
+        tenant: &TenantContext,
+        user_id: Uuid,
+        client_ip: Option<String>,
+        user_agent: Option<String>,
+        device_fingerprint: Option<String>,
+        limit: SessionLimit,
+    ) -> Result<CreatedSession> {
         let now = Utc::now();
         let session_id = Uuid::new_v4().to_string();
 
-        // Clean up old sessions for this user if we've exceeded the limit
-        self.enforce_session_limit(tenant, user_id).await?;
+        // Sessions that timed out must not count against the cap
+        self.prune_invalid_sessions(tenant, user_id).await?;
 
         let session = SessionData {
             session_id: session_id.clone(),
@@ -129,21 +276,67 @@ impl SessionManager {
             device_fingerprint: device_fingerprint.clone(),
         };
 
-        // Store session in Redis
-        self.store_session(&session).await?;
+        let serialized = serde_json::to_string(&session)
+            .map_err(|e| Error::new(ErrorCode::SerializationError, e.to_string()))?;
+        let ttl = self.config.absolute_timeout.num_seconds().max(1);
+        let mut conn = self.redis.clone();
 
-        // Add to user's session index
-        self.add_to_user_sessions(tenant, user_id, &session_id).await?;
+        let reply: Vec<String> = redis::Script::new(CREATE_SESSION_SCRIPT)
+            .key(self.user_sessions_key(tenant.tenant_id.0, user_id))
+            .key(self.session_key(tenant.tenant_id.0, &session_id))
+            .arg(format!("session:{}:", tenant.tenant_id.0))
+            .arg(&session_id)
+            .arg(serialized)
+            .arg(ttl)
+            .arg(limit.max_sessions_per_user.max(1))
+            .arg(limit.strategy.as_str())
+            .arg(ttl)
+            .invoke_async(&mut conn)
+            .await?;
+
+        let active_sessions = reply.get(1).and_then(|count| count.parse().ok()).unwrap_or(0);
+        if reply.first().map(String::as_str) != Some("1") {
+            warn!(
+                tenant_id = %tenant.tenant_id.0,
+                user_id = %user_id,
+                active_sessions = active_sessions,
+                "Session refused, user is at the session limit"
+            );
+            return Err(Error::new(
+                ErrorCode::ConcurrencyLimitExceeded,
+                format!("At most {} sessions may be active at once", limit.max_sessions_per_user),
+            )
+            .add_metadata("active_sessions", serde_json::json!(active_sessions)));
+        }
+
+        let evicted: Vec<SessionData> = reply
+            .iter()
+            .skip(2)
+            .filter_map(|data| serde_json::from_str(data).ok())
+            .collect();
+        for old in &evicted {
+            warn!(
+                tenant_id = %tenant.tenant_id.0,
+                user_id = %user_id,
+                session_id = %old.session_id,
+                "Removing session due to session limit exceeded"
+            );
+        }
 
         info!(
             tenant_id = %tenant.tenant_id.0,
             user_id = %user_id,
             session_id = %session_id,
             client_ip = ?client_ip,
+            active_sessions = active_sessions,
             "Session created successfully"
         );
 
-        Ok(session)
+        Ok(CreatedSession {
+            session,
+            active_sessions,
+            evicted,
+        })
     }
 
     /// Retrieve and validate a session
@@ -152,7 +345,7 @@ impl SessionManager {
         tenant: &TenantContext,
         session_id: &str,
     ) -> Result<Option<SessionData>> {
-        let session_key = self.session_key(tenant, session_id);
+        let session_key = self.session_key(tenant.tenant_id.0, session_id);
         let mut conn = self.redis.clone();
 
         let session_data: Option<String> = conn.get(&session_key).await?;
@@ -209,7 +402,7 @@ impl SessionManager {
         session_id: &str,
         reason: SessionState,
     ) -> Result<()> {
-        let session_key = self.session_key(tenant, session_id);
+        let session_key = self.session_key(tenant.tenant_id.0, session_id);
         let mut conn = self.redis.clone();
 
         // Get session data directly from Redis without validation to avoid recursion
@@ -244,7 +437,7 @@ impl SessionManager {
         user_id: Uuid,
         reason: SessionState,
     ) -> Result<u32> {
-        let user_sessions_key = self.user_sessions_key(tenant, user_id);
+        let user_sessions_key = self.user_sessions_key(tenant.tenant_id.0, user_id);
         let mut conn = self.redis.clone();
 
         // Get all session IDs for the user
@@ -271,13 +464,34 @@ impl SessionManager {
         Ok(invalidated_count)
     }
 
+    /// Invalidate every session of a tenant, whichever user it belongs to
+    pub async fn invalidate_tenant_sessions(&self, tenant: &TenantContext, reason: SessionState) -> Result<u32> {
+        let mut conn = self.redis.clone();
+        let session_keys = self.scan_keys(&mut conn, &format!("session:{}:*", tenant.tenant_id.0)).await?;
+        let index_keys = self.scan_keys(&mut conn, &format!("user_sessions:{}:*", tenant.tenant_id.0)).await?;
+
+        let invalidated_count = session_keys.len() as u32;
+        for keys in session_keys.chunks(100).chain(index_keys.chunks(100)) {
+            let _: u32 = conn.del(keys).await?;
+        }
+
+        info!(
+            tenant_id = %tenant.tenant_id.0,
+            invalidated_count = invalidated_count,
+            reason = ?reason,
+            "All tenant sessions invalidated"
+        );
+
+        Ok(invalidated_count)
+    }
+
     /// Get all active sessions for a user
     pub async fn get_user_sessions(
         &self,
         tenant: &TenantContext,
         user_id: Uuid,
     ) -> Result<Vec<SessionData>> {
-        let user_sessions_key = self.user_sessions_key(tenant, user_id);
+        let user_sessions_key = self.user_sessions_key(tenant.tenant_id.0, user_id);
         let mut conn = self.redis.clone();
 
         let session_ids: Vec<String> = conn.smembers(&user_sessions_key).await?;
@@ -332,7 +546,7 @@ impl SessionManager {
                         let _: u32 = conn.del(&session_key).await?;
 
                         // Remove from user session index
-                        let user_sessions_key = self.user_sessions_key(tenant, session.user_id);
+                        let user_sessions_key = self.user_sessions_key(tenant.tenant_id.0, session.user_id);
                         let _: u32 = conn.srem(&user_sessions_key, &session.session_id).await?;
 
                         cleaned_up += 1;
@@ -421,20 +635,16 @@ impl SessionManager {
         Ok(keys)
     }
 
-    fn session_key(&self, tenant: &TenantContext, session_id: &str) -> String {
-        format!("session:{}:{}", tenant.tenant_id.0, session_id)
+    fn session_key(&self, tenant_id: Uuid, session_id: &str) -> String {
+        format!("session:{}:{}", tenant_id, session_id)
     }
 
-    fn user_sessions_key(&self, tenant: &TenantContext, user_id: Uuid) -> String {
-        format!("user_sessions:{}:{}", tenant.tenant_id.0, user_id)
+    fn user_sessions_key(&self, tenant_id: Uuid, user_id: Uuid) -> String {
+        format!("user_sessions:{}:{}", tenant_id, user_id)
     }
 
     async fn store_session(&self, session: &SessionData) -> Result<()> {
-        let tenant_context = TenantContext {
-            tenant_id: crate::TenantId(session.tenant_id),
-            schema_name: format!("tenant_{}", session.tenant_id),
-        };
-        let session_key = self.session_key(&tenant_context, &session.session_id);
+        let session_key = self.session_key(session.tenant_id, &session.session_id);
         let mut conn = self.redis.clone();
 
         let serialized = serde_json::to_string(session)
@@ -449,57 +659,33 @@ impl SessionManager {
         Ok(())
     }
 
-    async fn add_to_user_sessions(
-        &self,
-        tenant: &TenantContext,
-        user_id: Uuid,
-        session_id: &str,
-    ) -> Result<()> {
-        let user_sessions_key = self.user_sessions_key(tenant, user_id);
-        let mut conn = self.redis.clone();
-
-        let _: u32 = conn.sadd(&user_sessions_key, session_id).await?;
-        
-        // Set TTL for user sessions index
-        let ttl = self.config.absolute_timeout.num_seconds().max(1) as i64;
-        let _: u32 = conn.expire(&user_sessions_key, ttl).await?;
-
-        Ok(())
-    }
-
     async fn remove_from_user_sessions(
         &self,
         tenant: &TenantContext,
         user_id: Uuid,
         session_id: &str,
     ) -> Result<()> {
-        let user_sessions_key = self.user_sessions_key(tenant, user_id);
+        let user_sessions_key = self.user_sessions_key(tenant.tenant_id.0, user_id);
         let mut conn = self.redis.clone();
 
         let _: u32 = conn.srem(&user_sessions_key, session_id).await?;
         Ok(())
     }
 
-    async fn enforce_session_limit(&self, tenant: &TenantContext, user_id: Uuid) -> Result<()> {
-        let user_sessions = self.get_user_sessions(tenant, user_id).await?;
-
-        if user_sessions.len() >= self.config.max_sessions_per_user as usize {
-            // Remove oldest sessions to make room for new one
-            let mut sessions_to_remove = user_sessions;
-            sessions_to_remove.sort_by(|a, b| a.created_at.cmp(&b.created_at));
-
-            let excess_count = sessions_to_remove.len() - self.config.max_sessions_per_user as usize + 1;
-
-            for session in sessions_to_remove.iter().take(excess_count) {
-                warn!(
-                    tenant_id = %tenant.tenant_id.0,
-                    user_id = %user_id,
-                    session_id = %session.session_id,
-                    "Removing session due to session limit exceeded"
-                );
+    /// Invalidate the user's sessions that expired, without touching the live ones
+    async fn prune_invalid_sessions(&self, tenant: &TenantContext, user_id: Uuid) -> Result<()> {
+        let user_sessions_key = self.user_sessions_key(tenant.tenant_id.0, user_id);
+        let mut conn = self.redis.clone();
 
-                self.invalidate_session(tenant, &session.session_id, SessionState::Revoked)
-                    .await?;
+        let session_ids: Vec<String> = conn.smembers(&user_sessions_key).await?;
+        for session_id in session_ids {
+            let data: Option<String> = conn.get(self.session_key(tenant.tenant_id.0, &session_id)).await?;
+            let valid = data
+                .and_then(|data| serde_json::from_str::<SessionData>(&data).ok())
+                .is_some_and(|session| self.is_session_valid(&session));
+            if !valid {
+                self.invalidate_session(tenant, &session_id, SessionState::Expired).await?;
+                let _: u32 = conn.srem(&user_sessions_key, &session_id).await?;
             }
         }
 
@@ -540,4 +726,105 @@ pub struct SessionStats {
     pub logged_out_sessions: u32,
     pub revoked_sessions: u32,
     pub suspended_sessions: u32,
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TenantId;
+    use std::sync::Arc;
+
+    /// A manager on a scratch Redis database (`ERP_TEST_REDIS_URL`, default db
+    /// 15 on localhost) and a tenant of its own, so runs do not see each other
+    async fn manager(max_sessions_per_user: u32, strategy: SessionLimitStrategy) -> (Arc<SessionManager>, TenantContext) {
+        let url = std::env::var("ERP_TEST_REDIS_URL").unwrap_or_else(|_| "redis://localhost:6379/15".to_string());
+        let redis = ConnectionManager::new(redis::Client::open(url).unwrap()).await.unwrap();
+        let config = SessionConfig {
+            max_sessions_per_user,
+            session_limit_strategy: strategy,
+            ..Default::default()
+        };
+        let tenant = TenantContext {
+            tenant_id: TenantId(Uuid::new_v4()),
+            schema_name: "tenant_session_test".to_string(),
+        };
+        (Arc::new(SessionManager::new(redis, config)), tenant)
+    }
+
+    async fn login_concurrently(manager: &Arc<SessionManager>, tenant: &TenantContext, user_id: Uuid, logins: usize) -> Vec<Result<SessionData>> {
+        let handles: Vec<_> = (0..logins)
+            .map(|_| {
+                let manager = manager.clone();
+                let tenant = tenant.clone();
+                tokio::spawn(async move { manager.create_session(&tenant, user_id, None, None, None).await })
+            })
+            .collect();
+        let mut results = Vec::new();
+        for handle in handles {
+            results.push(handle.await.unwrap());
+        }
+        results
+    }
+
+    #[test]
+    fn test_session_limit_strategy_names() {
+        for strategy in [SessionLimitStrategy::EvictOldest, SessionLimitStrategy::Reject] {
+            assert_eq!(SessionLimitStrategy::parse(strategy.as_str()), Some(strategy));
+        }
+        assert_eq!(SessionLimitStrategy::parse("evict_newest"), None);
+        assert_eq!(SessionConfig::default().session_limit().strategy, SessionLimitStrategy::EvictOldest);
+    }
+
+    #[tokio::test]
+    #[ignore = "requires Redis"]
+    async fn test_concurrent_logins_evicting_stay_at_the_cap() {
+        let (manager, tenant) = manager(3, SessionLimitStrategy::EvictOldest).await;
+        let user_id = Uuid::new_v4();
+
+        let results = login_concurrently(&manager, &tenant, user_id, 25).await;
+        assert!(results.iter().all(|result| result.is_ok()));
+        assert_eq!(manager.get_user_sessions(&tenant, user_id).await.unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    #[ignore = "requires Redis"]
+    async fn test_concurrent_logins_rejected_beyond_the_cap() {
+        let (manager, tenant) = manager(3, SessionLimitStrategy::Reject).await;
+        let user_id = Uuid::new_v4();
+
+        let results = login_concurrently(&manager, &tenant, user_id, 25).await;
+        assert_eq!(results.iter().filter(|result| result.is_ok()).count(), 3);
+        assert!(results
+            .iter()
+            .filter_map(|result| result.as_ref().err())
+            .all(|e| e.code == ErrorCode::ConcurrencyLimitExceeded));
+        assert_eq!(manager.get_user_sessions(&tenant, user_id).await.unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    #[ignore = "requires Redis"]
+    async fn test_evict_oldest_signs_out_the_oldest_session() {
+        let (manager, tenant) = manager(2, SessionLimitStrategy::EvictOldest).await;
+        let user_id = Uuid::new_v4();
+        let limit = SessionConfig::default().session_limit();
+        let limit = SessionLimit { max_sessions_per_user: 2, ..limit };
+
+        let first = manager.create_session(&tenant, user_id, None, None, None).await.unwrap();
+        let second = manager.create_session(&tenant, user_id, None, None, None).await.unwrap();
+        let third = manager
+            .create_session_within(&tenant, user_id, Some("10.0.0.3".to_string()), None, None, limit)
+            .await
+            .unwrap();
+
+        assert_eq!(third.active_sessions, 2);
+        assert_eq!(third.evicted.len(), 1);
+        assert_eq!(third.evicted[0].session_id, first.session_id);
+        assert!(manager.get_session(&tenant, &first.session_id).await.unwrap().is_none());
+        assert!(manager.get_session(&tenant, &second.session_id).await.unwrap().is_some());
+
+        // A tenant cap below the sessions already held evicts down to it
+        let tighter = SessionLimit { max_sessions_per_user: 1, ..limit };
+        let fourth = manager.create_session_within(&tenant, user_id, None, None, None, tighter).await.unwrap();
+        assert_eq!(fourth.active_sessions, 1);
+        assert_eq!(fourth.evicted.len(), 2);
+    }
+}
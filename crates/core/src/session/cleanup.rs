@@ -10,7 +10,7 @@ use tracing::{error, info};
 pub struct SessionCleanupService {
     session_manager: Arc<SessionManager>,
     cleanup_interval: Duration,
-    tenants: Vec<TenantId>,
+    tenants: Vec<TenantContext>,
     stats_history: HashMap<TenantId, Vec<SessionStatsSnapshot>>,
 }
 
@@ -27,7 +27,7 @@ impl SessionCleanupService {
     pub fn new(
         session_manager: Arc<SessionManager>,
         cleanup_interval: Duration,
-        tenants: Vec<TenantId>,
+        tenants: Vec<TenantContext>,
     ) -> Self {
         Self {
             session_manager,
@@ -86,13 +86,9 @@ impl SessionCleanupService {
         let cleanup_timestamp = Utc::now();
 
         let tenants = self.tenants.clone();
-        for tenant_id in &tenants {
-            let tenant_context = TenantContext {
-                tenant_id: *tenant_id,
-                schema_name: format!("tenant_{}", tenant_id.0),
-            };
-
-            match self.cleanup_tenant_sessions(&tenant_context).await {
+        for tenant_context in &tenants {
+            let tenant_id = &tenant_context.tenant_id;
+            match self.cleanup_tenant_sessions(tenant_context).await {
                 Ok((cleaned_count, stats)) => {
                     total_cleaned += cleaned_count;
 
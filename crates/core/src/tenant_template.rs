@@ -0,0 +1,44 @@
+//! # Tenant Schema Template
+//!
+//! Tenant schemas are created from `migrations/templates/tenant_schema_template.sql`
+//! and brought level with it by `erp-deploy tenant migrate-all`. The template's
+//! header names the shared migration its tables match (`-- Schema version: N`).
+//! It is raised only by migrations that change tables the template copies, so
+//! a migration of shared tables alone does not leave every tenant behind.
+//!
+//! Whatever creates or migrates a tenant schema records this version in
+//! `public.tenant_schema_migrations`; the tenant health check expects it there.
+
+/// Template that creates a tenant schema, `{TENANT_SCHEMA}` replaced by its name
+pub const TENANT_SCHEMA_TEMPLATE: &str = include_str!("../../../migrations/templates/tenant_schema_template.sql");
+
+/// Header line naming the template's version
+const VERSION_MARKER: &str = "-- Schema version:";
+
+/// Recorded as the description of the template's version in `tenant_schema_migrations`
+pub const TEMPLATE_VERSION_DESCRIPTION: &str = "tenant schema template";
+
+/// Version a template declares in its header, if any
+pub fn template_version(template: &str) -> Option<i64> {
+    template
+        .lines()
+        .find_map(|line| line.strip_prefix(VERSION_MARKER))
+        .and_then(|version| version.trim().parse().ok())
+}
+
+/// Version of the bundled template, which tenant schemas are expected to match
+pub fn tenant_template_version() -> i64 {
+    template_version(TENANT_SCHEMA_TEMPLATE).expect("the tenant schema template declares its version")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_template_version_is_read_from_the_header() {
+        assert_eq!(template_version("-- Tenant schema\n-- Schema version: 12\nCREATE SCHEMA x;"), Some(12));
+        assert_eq!(template_version("CREATE SCHEMA x;"), None);
+        assert!(tenant_template_version() > 0);
+    }
+}
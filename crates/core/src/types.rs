@@ -2,6 +2,10 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+pub mod address;
+
+pub use address::{Address, AddressFormat, AddressRule, AddressViolation, GeoPoint};
+
 // Axum integration for RequestContext
 #[cfg(feature = "axum")]
 use axum::{
@@ -125,6 +129,13 @@ pub struct JwtClaims {
     pub iat: i64,
     pub jti: String, // JWT ID for revocation
     pub impersonator_id: Option<String>,
+    /// Present on customer portal accounts only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub portal: Option<crate::portal::PortalScope>,
+    /// Session the token was issued for; tokens from before sessions were
+    /// bound to them have none
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -151,8 +162,12 @@ pub struct RequestContext {
     pub tenant_context: Option<TenantContext>,  // Optional for handler compatibility
     pub user_id: Option<Uuid>,                 // Optional for handler compatibility  
     pub jti: Option<String>,                   // JWT ID for logout functionality
+    /// Session the access token belongs to, if it is bound to one
+    pub session_id: Option<String>,
     pub permissions: Vec<Permission>,
     pub impersonator_id: Option<UserId>,
+    /// The customer a portal account is bound to; `None` for tenant users
+    pub portal: Option<crate::portal::PortalScope>,
     pub request_id: String,
 }
 
@@ -162,8 +177,10 @@ impl RequestContext {
             tenant_context: None,
             user_id: None,
             jti: None,
+            session_id: None,
             permissions: Vec::new(),
             impersonator_id: None,
+            portal: None,
             request_id: uuid::Uuid::new_v4().to_string(),
         }
     }
@@ -192,6 +209,15 @@ impl RequestContext {
         self.permissions = permissions;
         self
     }
+
+    /// Who the request acts as; no user if it was not authenticated
+    pub fn actor(&self) -> crate::actor::ActorContext {
+        crate::actor::ActorContext {
+            user_id: self.user_id,
+            service_account_id: None,
+            impersonated_by: self.impersonator_id.map(|id| id.0),
+        }
+    }
 }
 
 // Axum FromRequestParts implementation for RequestContext
@@ -0,0 +1,522 @@
+//! # Postal Addresses
+//!
+//! [`Address`] is the value type shared by every module that stores an
+//! address. It is validated in two steps:
+//!
+//! 1. [`Address::normalized`] trims every field, collapses inner whitespace,
+//!    drops empty lines and uppercases the country and postal code.
+//! 2. [`Address::validate`] checks the generic rules (ISO 3166-1 alpha-2
+//!    country, line count and length, city) and, for the countries listed in
+//!    [`country_rules`], the postal code format and whether a region is
+//!    required. Unknown countries only get the generic rules.
+//!
+//! Every violation names the [`AddressRule`] it broke so callers can report
+//! exactly what is wrong. [`AddressFormat`] renders an address the way the
+//! destination country writes it, for exports and emails.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::{PgArgumentBuffer, PgTypeInfo, PgValueRef, Postgres};
+use sqlx::types::Json;
+use std::fmt;
+
+/// Most lines an address may have before the city
+pub const MAX_ADDRESS_LINES: usize = 4;
+/// Longest allowed address line, city or region
+pub const MAX_LINE_LENGTH: usize = 100;
+/// Longest allowed postal code for countries without a known format
+pub const MAX_POSTAL_CODE_LENGTH: usize = 20;
+
+/// Position on the globe in decimal degrees
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GeoPoint {
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+/// Postal address, stored as JSON
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Address {
+    /// ISO 3166-1 alpha-2
+    pub country_code: String,
+    /// Street, building, unit etc. in writing order
+    pub lines: Vec<String>,
+    pub city: String,
+    /// State, province or county
+    #[serde(default)]
+    pub region: Option<String>,
+    #[serde(default)]
+    pub postal_code: Option<String>,
+    #[serde(default)]
+    pub coordinates: Option<GeoPoint>,
+}
+
+/// A validation rule an address can break
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AddressRule {
+    /// Country code is not two letters
+    CountryCode,
+    /// At least one address line is needed
+    LineRequired,
+    /// More than [`MAX_ADDRESS_LINES`] lines
+    TooManyLines,
+    /// A line, the city or the region is longer than [`MAX_LINE_LENGTH`]
+    LineLength,
+    CityRequired,
+    /// The country requires a region
+    RegionRequired,
+    /// The country requires a postal code
+    PostalCodeRequired,
+    /// The postal code doesn't match the country's format
+    PostalCodeFormat,
+    /// Latitude or longitude out of range
+    Coordinates,
+}
+
+impl AddressRule {
+    /// Stable identifier used as the error code
+    pub fn code(&self) -> &'static str {
+        match self {
+            AddressRule::CountryCode => "address.country_code",
+            AddressRule::LineRequired => "address.line_required",
+            AddressRule::TooManyLines => "address.too_many_lines",
+            AddressRule::LineLength => "address.line_length",
+            AddressRule::CityRequired => "address.city_required",
+            AddressRule::RegionRequired => "address.region_required",
+            AddressRule::PostalCodeRequired => "address.postal_code_required",
+            AddressRule::PostalCodeFormat => "address.postal_code_format",
+            AddressRule::Coordinates => "address.coordinates",
+        }
+    }
+}
+
+/// One broken rule, with the field it applies to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AddressViolation {
+    /// Field path within the address, e.g. `lines[1]` or `postal_code`
+    pub field: String,
+    pub rule: AddressRule,
+    pub message: String,
+}
+
+impl fmt::Display for AddressViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} [{}]: {}", self.field, self.rule.code(), self.message)
+    }
+}
+
+/// How the city, region and postal code are arranged when formatting
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LocalityLayout {
+    /// `City REGION POSTAL`, as in North America and Australia
+    CityRegionPostal,
+    /// `POSTAL City`, as in most of continental Europe
+    PostalCity,
+    /// City and postal code on separate lines, as in the UK
+    CityThenPostal,
+}
+
+/// Country-specific address rules
+#[derive(Debug)]
+pub struct CountryRules {
+    pub country_code: &'static str,
+    postal_code: Regex,
+    /// Example shown in postal code errors
+    pub postal_code_example: &'static str,
+    pub region_required: bool,
+    pub layout: LocalityLayout,
+}
+
+impl CountryRules {
+    fn new(
+        country_code: &'static str,
+        postal_code: &str,
+        postal_code_example: &'static str,
+        region_required: bool,
+        layout: LocalityLayout,
+    ) -> Self {
+        Self {
+            country_code,
+            postal_code: Regex::new(postal_code).unwrap(),
+            postal_code_example,
+            region_required,
+            layout,
+        }
+    }
+
+    pub fn postal_code_matches(&self, postal_code: &str) -> bool {
+        self.postal_code.is_match(postal_code)
+    }
+}
+
+static COUNTRY_RULES: Lazy<Vec<CountryRules>> = Lazy::new(|| {
+    use LocalityLayout::*;
+    vec![
+        CountryRules::new("US", r"^\d{5}(-\d{4})?$", "94105 or 94105-1234", true, CityRegionPostal),
+        CountryRules::new(
+            "CA",
+            r"^[ABCEGHJ-NPRSTVXY]\d[ABCEGHJ-NPRSTV-Z] \d[ABCEGHJ-NPRSTV-Z]\d$",
+            "K1A 0B1",
+            true,
+            CityRegionPostal,
+        ),
+        CountryRules::new(
+            "GB",
+            r"^([A-Z]{1,2}\d[A-Z\d]? \d[A-Z]{2}|GIR 0AA)$",
+            "SW1A 1AA",
+            false,
+            CityThenPostal,
+        ),
+        CountryRules::new("DE", r"^\d{5}$", "10115", false, PostalCity),
+        CountryRules::new("FR", r"^\d{5}$", "75008", false, PostalCity),
+        CountryRules::new("AU", r"^\d{4}$", "2000", true, CityRegionPostal),
+        CountryRules::new("CH", r"^\d{4}$", "8001", false, PostalCity),
+    ]
+});
+
+static COUNTRY_CODE_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[A-Z]{2}$").unwrap());
+
+/// Rules for `country_code`, or `None` for countries without specific rules
+pub fn country_rules(country_code: &str) -> Option<&'static CountryRules> {
+    COUNTRY_RULES.iter().find(|rules| rules.country_code == country_code)
+}
+
+/// Trim and collapse runs of whitespace to a single space
+fn clean(value: &str) -> String {
+    value.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn clean_optional(value: Option<&str>) -> Option<String> {
+    value.map(clean).filter(|v| !v.is_empty())
+}
+
+impl Address {
+    /// Copy with whitespace cleaned up, empty lines dropped and the country and postal code uppercased
+    pub fn normalized(&self) -> Self {
+        let postal_code = clean_optional(self.postal_code.as_deref()).map(|p| p.to_uppercase());
+        let postal_code = match (self.country_code.trim().to_uppercase().as_str(), postal_code) {
+            // Canadian and British codes are often written without the space
+            ("CA", Some(p)) if p.len() == 6 && p.is_ascii() && !p.contains(' ') => Some(format!("{} {}", &p[..3], &p[3..])),
+            ("GB", Some(p)) if (5..=7).contains(&p.len()) && p.is_ascii() && !p.contains(' ') => {
+                Some(format!("{} {}", &p[..p.len() - 3], &p[p.len() - 3..]))
+            }
+            (_, p) => p,
+        };
+
+        Self {
+            country_code: self.country_code.trim().to_uppercase(),
+            lines: self.lines.iter().map(|l| clean(l)).filter(|l| !l.is_empty()).collect(),
+            city: clean(&self.city),
+            region: clean_optional(self.region.as_deref()),
+            postal_code,
+            coordinates: self.coordinates,
+        }
+    }
+
+    /// Check the address as given; call [`Address::normalized`] first to accept sloppy input
+    pub fn validate(&self) -> Result<(), Vec<AddressViolation>> {
+        let mut violations = Vec::new();
+        let mut violate = |field: &str, rule: AddressRule, message: String| {
+            violations.push(AddressViolation {
+                field: field.to_string(),
+                rule,
+                message,
+            });
+        };
+
+        let known_country = COUNTRY_CODE_REGEX.is_match(&self.country_code);
+        if !known_country {
+            violate(
+                "country_code",
+                AddressRule::CountryCode,
+                format!("'{}' is not an ISO 3166-1 alpha-2 country code", self.country_code),
+            );
+        }
+
+        if self.lines.iter().all(|l| l.trim().is_empty()) {
+            violate("lines", AddressRule::LineRequired, "At least one address line is required".to_string());
+        }
+        if self.lines.len() > MAX_ADDRESS_LINES {
+            violate(
+                "lines",
+                AddressRule::TooManyLines,
+                format!("At most {} address lines are allowed", MAX_ADDRESS_LINES),
+            );
+        }
+        for (i, line) in self.lines.iter().enumerate() {
+            if line.chars().count() > MAX_LINE_LENGTH {
+                violate(
+                    &format!("lines[{}]", i),
+                    AddressRule::LineLength,
+                    format!("Address lines cannot exceed {} characters", MAX_LINE_LENGTH),
+                );
+            }
+        }
+
+        if self.city.trim().is_empty() {
+            violate("city", AddressRule::CityRequired, "City is required".to_string());
+        } else if self.city.chars().count() > MAX_LINE_LENGTH {
+            violate("city", AddressRule::LineLength, format!("City cannot exceed {} characters", MAX_LINE_LENGTH));
+        }
+        if self.region.as_ref().is_some_and(|r| r.chars().count() > MAX_LINE_LENGTH) {
+            violate("region", AddressRule::LineLength, format!("Region cannot exceed {} characters", MAX_LINE_LENGTH));
+        }
+
+        let rules = if known_country { country_rules(&self.country_code) } else { None };
+        let region_missing = self.region.as_ref().is_none_or(|r| r.trim().is_empty());
+        match (rules, self.postal_code.as_deref()) {
+            (Some(rules), None) => violate(
+                "postal_code",
+                AddressRule::PostalCodeRequired,
+                format!("Postal code is required for {}", rules.country_code),
+            ),
+            (Some(rules), Some(postal_code)) if !rules.postal_code_matches(postal_code) => violate(
+                "postal_code",
+                AddressRule::PostalCodeFormat,
+                format!(
+                    "'{}' is not a valid {} postal code (expected e.g. {})",
+                    postal_code, rules.country_code, rules.postal_code_example
+                ),
+            ),
+            (None, Some(postal_code)) if postal_code.chars().count() > MAX_POSTAL_CODE_LENGTH => violate(
+                "postal_code",
+                AddressRule::PostalCodeFormat,
+                format!("Postal code cannot exceed {} characters", MAX_POSTAL_CODE_LENGTH),
+            ),
+            _ => {}
+        }
+        if let Some(rules) = rules.filter(|r| r.region_required && region_missing) {
+            violate(
+                "region",
+                AddressRule::RegionRequired,
+                format!("A state or province is required for {}", rules.country_code),
+            );
+        }
+
+        if let Some(point) = self.coordinates {
+            if !(-90.0..=90.0).contains(&point.latitude) || !(-180.0..=180.0).contains(&point.longitude) {
+                violate(
+                    "coordinates",
+                    AddressRule::Coordinates,
+                    "Latitude must be within ±90 and longitude within ±180 degrees".to_string(),
+                );
+            }
+        }
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+
+    /// Normalize, then validate; returns the normalized address
+    pub fn normalize_and_validate(&self) -> Result<Self, Vec<AddressViolation>> {
+        let normalized = self.normalized();
+        normalized.validate()?;
+        Ok(normalized)
+    }
+}
+
+/// Formatting an address per the destination country's convention
+pub trait AddressFormat {
+    /// Lines as printed on an envelope, ending with the country code
+    fn formatted_lines(&self) -> Vec<String>;
+
+    /// All lines joined with newlines, for emails and letters
+    fn to_multiline(&self) -> String {
+        self.formatted_lines().join("\n")
+    }
+
+    /// All lines joined with commas, for CSV exports
+    fn to_single_line(&self) -> String {
+        self.formatted_lines().join(", ")
+    }
+}
+
+impl AddressFormat for Address {
+    fn formatted_lines(&self) -> Vec<String> {
+        let layout = country_rules(&self.country_code)
+            .map(|rules| rules.layout)
+            .unwrap_or(LocalityLayout::CityRegionPostal);
+        let join = |parts: &[Option<&str>]| parts.iter().flatten().copied().collect::<Vec<_>>().join(" ");
+
+        let mut lines = self.lines.clone();
+        let postal_code = self.postal_code.as_deref();
+        let region = self.region.as_deref();
+        match layout {
+            LocalityLayout::CityRegionPostal => {
+                let city = if region.is_some() || postal_code.is_some() {
+                    format!("{},", self.city)
+                } else {
+                    self.city.clone()
+                };
+                lines.push(join(&[Some(&city), region, postal_code]));
+            }
+            LocalityLayout::PostalCity => {
+                lines.push(join(&[postal_code, Some(&self.city)]));
+                lines.extend(region.map(str::to_string));
+            }
+            LocalityLayout::CityThenPostal => {
+                lines.push(self.city.to_uppercase());
+                lines.extend(region.map(str::to_string));
+                lines.extend(postal_code.map(str::to_string));
+            }
+        }
+        lines.push(self.country_code.clone());
+        lines
+    }
+}
+
+impl sqlx::Type<Postgres> for Address {
+    fn type_info() -> PgTypeInfo {
+        <Json<Address> as sqlx::Type<Postgres>>::type_info()
+    }
+
+    fn compatible(ty: &PgTypeInfo) -> bool {
+        <Json<Address> as sqlx::Type<Postgres>>::compatible(ty)
+    }
+}
+
+impl sqlx::Encode<'_, Postgres> for Address {
+    fn encode_by_ref(&self, buf: &mut PgArgumentBuffer) -> Result<sqlx::encode::IsNull, sqlx::error::BoxDynError> {
+        Json(self).encode_by_ref(buf)
+    }
+}
+
+impl<'r> sqlx::Decode<'r, Postgres> for Address {
+    fn decode(value: PgValueRef<'r>) -> Result<Self, sqlx::error::BoxDynError> {
+        Ok(<Json<Address> as sqlx::Decode<'r, Postgres>>::decode(value)?.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn address(country: &str, lines: &[&str], city: &str, region: Option<&str>, postal: Option<&str>) -> Address {
+        Address {
+            country_code: country.to_string(),
+            lines: lines.iter().map(|l| l.to_string()).collect(),
+            city: city.to_string(),
+            region: region.map(str::to_string),
+            postal_code: postal.map(str::to_string),
+            coordinates: None,
+        }
+    }
+
+    fn rules_broken(address: &Address) -> Vec<AddressRule> {
+        match address.normalize_and_validate() {
+            Ok(_) => vec![],
+            Err(violations) => violations.into_iter().map(|v| v.rule).collect(),
+        }
+    }
+
+    #[test]
+    fn test_normalization() {
+        let raw = address(" us ", &["  1  Market   St ", "   "], " San  Francisco ", Some(" CA "), Some(" 94105 "));
+        let normalized = raw.normalized();
+        assert_eq!(normalized.country_code, "US");
+        assert_eq!(normalized.lines, vec!["1 Market St"]);
+        assert_eq!(normalized.city, "San Francisco");
+        assert_eq!(normalized.region.as_deref(), Some("CA"));
+        assert_eq!(normalized.postal_code.as_deref(), Some("94105"));
+
+        let blank_region = address("DE", &["Unter den Linden 1"], "Berlin", Some("  "), Some("10117"));
+        assert_eq!(blank_region.normalized().region, None);
+    }
+
+    #[test]
+    fn test_us() {
+        assert!(rules_broken(&address("US", &["1 Market St"], "San Francisco", Some("CA"), Some("94105"))).is_empty());
+        assert!(rules_broken(&address("US", &["1 Market St"], "San Francisco", Some("CA"), Some("94105-1234"))).is_empty());
+        assert_eq!(
+            rules_broken(&address("US", &["1 Market St"], "San Francisco", Some("CA"), Some("9410"))),
+            vec![AddressRule::PostalCodeFormat]
+        );
+        assert_eq!(
+            rules_broken(&address("US", &["1 Market St"], "San Francisco", None, Some("94105"))),
+            vec![AddressRule::RegionRequired]
+        );
+    }
+
+    #[test]
+    fn test_ca() {
+        let spaced = address("CA", &["24 Sussex Dr"], "Ottawa", Some("ON"), Some("k1m 1m4"));
+        assert_eq!(spaced.normalize_and_validate().unwrap().postal_code.as_deref(), Some("K1M 1M4"));
+        let unspaced = address("CA", &["24 Sussex Dr"], "Ottawa", Some("ON"), Some("K1M1M4"));
+        assert_eq!(unspaced.normalize_and_validate().unwrap().postal_code.as_deref(), Some("K1M 1M4"));
+        // D, F, I, O, Q and U are never used
+        assert_eq!(
+            rules_broken(&address("CA", &["24 Sussex Dr"], "Ottawa", Some("ON"), Some("D1M 1M4"))),
+            vec![AddressRule::PostalCodeFormat]
+        );
+    }
+
+    #[test]
+    fn test_gb() {
+        let unspaced = address("GB", &["10 Downing St"], "London", None, Some("sw1a2aa"));
+        assert_eq!(unspaced.normalize_and_validate().unwrap().postal_code.as_deref(), Some("SW1A 2AA"));
+        assert!(rules_broken(&address("GB", &["1 Deansgate"], "Manchester", None, Some("M3 1AZ"))).is_empty());
+        assert_eq!(
+            rules_broken(&address("GB", &["10 Downing St"], "London", None, Some("12345"))),
+            vec![AddressRule::PostalCodeFormat]
+        );
+    }
+
+    #[test]
+    fn test_de_fr_ch_au() {
+        assert!(rules_broken(&address("DE", &["Unter den Linden 1"], "Berlin", None, Some("10117"))).is_empty());
+        assert_eq!(
+            rules_broken(&address("DE", &["Unter den Linden 1"], "Berlin", None, Some("1011"))),
+            vec![AddressRule::PostalCodeFormat]
+        );
+        assert!(rules_broken(&address("FR", &["55 Rue du Faubourg Saint-Honoré"], "Paris", None, Some("75008"))).is_empty());
+        assert_eq!(
+            rules_broken(&address("FR", &["55 Rue du Faubourg Saint-Honoré"], "Paris", None, None)),
+            vec![AddressRule::PostalCodeRequired]
+        );
+        assert!(rules_broken(&address("CH", &["Bahnhofstrasse 1"], "Zürich", None, Some("8001"))).is_empty());
+        assert_eq!(
+            rules_broken(&address("CH", &["Bahnhofstrasse 1"], "Zürich", None, Some("80010"))),
+            vec![AddressRule::PostalCodeFormat]
+        );
+        assert!(rules_broken(&address("AU", &["1 Macquarie St"], "Sydney", Some("NSW"), Some("2000"))).is_empty());
+        assert_eq!(
+            rules_broken(&address("AU", &["1 Macquarie St"], "Sydney", None, Some("2000"))),
+            vec![AddressRule::RegionRequired]
+        );
+    }
+
+    #[test]
+    fn test_unknown_country_only_gets_generic_rules() {
+        // Any postal code format and no region are fine
+        assert!(rules_broken(&address("NL", &["Dam 1"], "Amsterdam", None, Some("1012 JS"))).is_empty());
+        assert!(rules_broken(&address("IE", &["1 O'Connell St"], "Dublin", None, None)).is_empty());
+
+        let long_line = "x".repeat(MAX_LINE_LENGTH + 1);
+        let broken = address("NLD", &[&long_line], "", None, Some(&"9".repeat(MAX_POSTAL_CODE_LENGTH + 1)));
+        let violations = broken.normalize_and_validate().unwrap_err();
+        let rules: Vec<_> = violations.iter().map(|v| v.rule).collect();
+        assert_eq!(
+            rules,
+            vec![AddressRule::CountryCode, AddressRule::LineLength, AddressRule::CityRequired, AddressRule::PostalCodeFormat]
+        );
+        assert_eq!(violations[1].field, "lines[0]");
+        assert!(violations[1].to_string().contains("address.line_length"));
+    }
+
+    #[test]
+    fn test_formatting_follows_country_convention() {
+        let us = address("US", &["1 Market St", "Suite 300"], "San Francisco", Some("CA"), Some("94105"));
+        assert_eq!(us.to_multiline(), "1 Market St\nSuite 300\nSan Francisco, CA 94105\nUS");
+
+        let de = address("DE", &["Unter den Linden 1"], "Berlin", None, Some("10117"));
+        assert_eq!(de.to_single_line(), "Unter den Linden 1, 10117 Berlin, DE");
+
+        let gb = address("GB", &["10 Downing St"], "London", None, Some("SW1A 2AA"));
+        assert_eq!(gb.formatted_lines(), vec!["10 Downing St", "LONDON", "SW1A 2AA", "GB"]);
+    }
+}
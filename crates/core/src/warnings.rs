@@ -0,0 +1,159 @@
+//! # Response Warnings
+//!
+//! Non-fatal issues of an otherwise successful request: the operation went
+//! through, but the caller should know that something was skipped, stored
+//! unchecked or left out. Any layer raises one with [`warn`] without passing
+//! a collector around; the API collects them per request with [`collect`]
+//! and returns them as a `warnings` array next to the response body. A
+//! warning never changes the response status.
+//!
+//! Warnings live in a task-local scope. Outside a [`collect`] scope, and in
+//! tasks spawned from the request, [`warn`] only logs the warning.
+
+use serde::Serialize;
+use std::cell::RefCell;
+use std::future::Future;
+
+/// Response header carrying warnings of responses without a JSON object body
+pub const WARNING_HEADER: &str = "x-warning";
+
+/// What a warning is about; listed in the error catalog next to the error codes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum WarningCode {
+    /// A tax number has no format that can be verified; it was stored as given
+    TaxIdUnverified,
+    /// The AI check of product data was unavailable; the product was saved without it
+    AiValidationSkipped,
+    /// Values were masked in an export and are not part of it
+    MaskedFieldsOmitted,
+    /// A receipt or transfer fills a location or bin close to its capacity
+    LocationNearCapacity,
+    /// A product has no dimensions; its storage space was estimated
+    SpaceFactorDefaulted,
+    /// A location or bin was filled past its capacity by an override
+    CapacityOverridden,
+}
+
+impl WarningCode {
+    /// Every warning code, in declaration order
+    pub const ALL: [WarningCode; 6] = [
+        WarningCode::TaxIdUnverified,
+        WarningCode::AiValidationSkipped,
+        WarningCode::MaskedFieldsOmitted,
+        WarningCode::LocationNearCapacity,
+        WarningCode::SpaceFactorDefaulted,
+        WarningCode::CapacityOverridden,
+    ];
+
+    /// The code as it appears in responses
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WarningCode::TaxIdUnverified => "TAX_ID_UNVERIFIED",
+            WarningCode::AiValidationSkipped => "AI_VALIDATION_SKIPPED",
+            WarningCode::MaskedFieldsOmitted => "MASKED_FIELDS_OMITTED",
+            WarningCode::LocationNearCapacity => "LOCATION_NEAR_CAPACITY",
+            WarningCode::SpaceFactorDefaulted => "SPACE_FACTOR_DEFAULTED",
+            WarningCode::CapacityOverridden => "CAPACITY_OVERRIDDEN",
+        }
+    }
+
+    /// What the warning means for the caller, for the error catalog
+    pub fn description(&self) -> &'static str {
+        match self {
+            WarningCode::TaxIdUnverified => "A tax number could not be verified and was stored as given",
+            WarningCode::AiValidationSkipped => "Product data was saved without the AI validation check",
+            WarningCode::MaskedFieldsOmitted => "Masked values were left out of the export",
+            WarningCode::LocationNearCapacity => "The location or bin is filled close to its storage capacity",
+            WarningCode::SpaceFactorDefaulted => "A product without dimensions was counted with the default storage space",
+            WarningCode::CapacityOverridden => "The location or bin was filled past its capacity by an override",
+        }
+    }
+}
+
+/// One non-fatal issue of a request
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Warning {
+    pub code: WarningCode,
+    pub message: String,
+    /// Request or response field the warning is about
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+}
+
+impl Warning {
+    pub fn new(code: WarningCode, message: impl Into<String>) -> Self {
+        Self { code, message: message.into(), field: None }
+    }
+
+    pub fn with_field(mut self, field: impl Into<String>) -> Self {
+        self.field = Some(field.into());
+        self
+    }
+}
+
+tokio::task_local! {
+    static WARNINGS: RefCell<Vec<Warning>>;
+}
+
+/// Add a warning to the current request; the same warning twice is kept once
+pub fn warn(warning: Warning) {
+    tracing::debug!("Warning {}: {}", warning.code.as_str(), warning.message);
+    let _ = WARNINGS.try_with(|warnings| {
+        let mut warnings = warnings.borrow_mut();
+        if !warnings.contains(&warning) {
+            warnings.push(warning);
+        }
+    });
+}
+
+/// Run `future` in a fresh warning scope and return its output with the
+/// warnings raised while it ran, in the order they were raised
+pub async fn collect<F: Future>(future: F) -> (F::Output, Vec<Warning>) {
+    WARNINGS
+        .scope(RefCell::new(Vec::new()), async move {
+            let output = future.await;
+            (output, WARNINGS.with(|warnings| warnings.take()))
+        })
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_collects_warnings_of_nested_calls_once() {
+        async fn service() {
+            warn(Warning::new(WarningCode::TaxIdUnverified, "unchecked").with_field("tax_numbers.vat"));
+            warn(Warning::new(WarningCode::TaxIdUnverified, "unchecked").with_field("tax_numbers.vat"));
+        }
+
+        let (value, warnings) = collect(async {
+            service().await;
+            warn(Warning::new(WarningCode::MaskedFieldsOmitted, "masked"));
+            7
+        })
+        .await;
+
+        assert_eq!(value, 7);
+        let codes: Vec<_> = warnings.iter().map(|w| w.code).collect();
+        assert_eq!(codes, [WarningCode::TaxIdUnverified, WarningCode::MaskedFieldsOmitted]);
+        assert_eq!(
+            serde_json::to_value(&warnings[1]).unwrap(),
+            serde_json::json!({ "code": "MASKED_FIELDS_OMITTED", "message": "masked" })
+        );
+
+        // Outside a scope nothing is kept and nothing fails
+        warn(Warning::new(WarningCode::AiValidationSkipped, "skipped"));
+        let ((), warnings) = collect(async {}).await;
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn test_codes_serialize_as_listed() {
+        for code in WarningCode::ALL {
+            assert_eq!(serde_json::to_value(code).unwrap(), code.as_str());
+        }
+    }
+}
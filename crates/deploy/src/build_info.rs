@@ -0,0 +1,82 @@
+//! Build metadata of the CLI and its compatibility with API server builds
+//!
+//! The CLI embeds the same metadata as the API server (see `erp-build-info`), so
+//! `erp-deploy status` can compare both sides of a deployment.
+
+pub use erp_build_info::BuildInfo;
+
+/// Metadata of this CLI binary
+pub fn current_build() -> BuildInfo {
+    erp_build_info::build_info!()
+}
+
+/// API server releases each CLI release can manage, keyed by CLI `major.minor`
+///
+/// Extend this when cutting a release whose schema or endpoints change.
+const COMPATIBILITY_MATRIX: &[(&str, &[&str])] = &[
+    ("0.1", &["0.1"]),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Compatibility {
+    /// Listed as supported in the matrix
+    Compatible,
+    /// The CLI release is in the matrix but does not support this API release
+    Incompatible { supported: Vec<String> },
+    /// The CLI release is missing from the matrix or a version did not parse
+    Unknown,
+}
+
+/// Check whether a CLI release supports an API server release
+pub fn check_compatibility(cli_version: &str, api_version: &str) -> Compatibility {
+    let (Some(cli), Some(api)) = (major_minor(cli_version), major_minor(api_version)) else {
+        return Compatibility::Unknown;
+    };
+
+    match COMPATIBILITY_MATRIX.iter().find(|(release, _)| *release == cli) {
+        Some((_, supported)) if supported.contains(&api.as_str()) => Compatibility::Compatible,
+        Some((_, supported)) => Compatibility::Incompatible {
+            supported: supported.iter().map(|v| v.to_string()).collect(),
+        },
+        None => Compatibility::Unknown,
+    }
+}
+
+/// `major.minor` of a semantic version, ignoring patch and pre-release parts
+fn major_minor(version: &str) -> Option<String> {
+    let mut parts = version.trim().trim_start_matches('v').split('.');
+    let major: u64 = parts.next()?.parse().ok()?;
+    let minor: u64 = parts.next()?.split(['-', '+']).next()?.parse().ok()?;
+    Some(format!("{}.{}", major, minor))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_current_release_is_in_matrix() {
+        let current = current_build();
+        assert_eq!(check_compatibility(&current.version, &current.version), Compatibility::Compatible);
+    }
+
+    #[test]
+    fn test_patch_releases_are_compatible() {
+        assert_eq!(check_compatibility("0.1.0", "0.1.7"), Compatibility::Compatible);
+        assert_eq!(check_compatibility("v0.1.3", "0.1.0-rc.1"), Compatibility::Compatible);
+    }
+
+    #[test]
+    fn test_unsupported_api_release_is_incompatible() {
+        assert_eq!(
+            check_compatibility("0.1.0", "0.2.0"),
+            Compatibility::Incompatible { supported: vec!["0.1".to_string()] }
+        );
+    }
+
+    #[test]
+    fn test_unknown_releases() {
+        assert_eq!(check_compatibility("9.9.0", "0.1.0"), Compatibility::Unknown);
+        assert_eq!(check_compatibility("0.1.0", "garbage"), Compatibility::Unknown);
+    }
+}
@@ -0,0 +1,310 @@
+//! Two-person approval for destructive operations
+//!
+//! In production a destructive operation such as `database reset` is first
+//! filed as a request that records exactly what it will do (its scope). A
+//! second operator approves it with `erp-deploy database approve <id>`, and the
+//! requester re-runs the operation with `--approval <id>`. An approval is good
+//! for one run of the same operation and scope by the operator who filed it,
+//! within [`APPROVAL_TTL_MINUTES`] of filing, and never when requester and
+//! approver are the same operator.
+//!
+//! Requests live in `erp_deploy.approvals`, outside the schemas a reset drops.
+
+use chrono::{DateTime, Duration, Utc};
+use colored::*;
+use serde::Serialize;
+use sqlx::{PgConnection, PgPool, Row};
+use uuid::Uuid;
+
+use crate::error::{DbResultExt, DeployError, Result};
+
+/// How long a filed request can be approved and used
+pub const APPROVAL_TTL_MINUTES: i64 = 60;
+
+/// A filed request for a destructive operation
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ApprovalRequest {
+    /// Short identifier passed to `database approve` and `--approval`
+    pub id: String,
+    /// e.g. `database reset`
+    pub operation: String,
+    /// What exactly will be done, e.g. `tenant=acme preserve=users,roles`
+    pub scope: String,
+    pub requested_by: String,
+    pub requested_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub approved_by: Option<String>,
+    pub approved_at: Option<DateTime<Utc>>,
+    pub used_at: Option<DateTime<Utc>>,
+}
+
+impl ApprovalRequest {
+    pub fn new(operation: &str, scope: &str, requested_by: &str, now: DateTime<Utc>) -> Self {
+        Self {
+            id: Uuid::new_v4().simple().to_string()[..12].to_string(),
+            operation: operation.to_string(),
+            scope: scope.to_string(),
+            requested_by: requested_by.to_string(),
+            requested_at: now,
+            expires_at: now + Duration::minutes(APPROVAL_TTL_MINUTES),
+            approved_by: None,
+            approved_at: None,
+            used_at: None,
+        }
+    }
+}
+
+/// Environments where destructive operations need a second operator
+pub fn requires_approval(environment: &str) -> bool {
+    matches!(environment.trim().to_ascii_lowercase().as_str(), "production" | "prod")
+}
+
+/// `user@host` of whoever runs the CLI
+pub fn operator_identity() -> String {
+    let user = std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_else(|_| "unknown".to_string());
+    let host = hostname::get()
+        .map(|h| h.to_string_lossy().to_string())
+        .unwrap_or_else(|_| "unknown".to_string());
+    format!("{}@{}", user, host)
+}
+
+fn denied(request: &ApprovalRequest, reason: impl Into<String>, request_id: Option<&str>) -> DeployError {
+    DeployError::ApprovalRequired {
+        operation: request.operation.clone(),
+        reason: reason.into(),
+        request_id: request_id.map(str::to_string),
+    }
+}
+
+/// Whether `approver` may approve the request
+pub fn check_approver(request: &ApprovalRequest, approver: &str, now: DateTime<Utc>) -> Result<()> {
+    if request.approved_by.is_some() {
+        return Err(DeployError::invalid_input(format!("Request {} is already approved", request.id)));
+    }
+    if now >= request.expires_at {
+        return Err(denied(request, format!("request {} expired at {}", request.id, request.expires_at), None));
+    }
+    if approver == request.requested_by {
+        return Err(denied(
+            request,
+            format!("{} filed request {} and cannot approve it too", approver, request.id),
+            Some(&request.id),
+        ));
+    }
+    Ok(())
+}
+
+/// Whether `operator` may now run `operation` on `scope` under the request
+pub fn check_approval(
+    request: &ApprovalRequest,
+    operation: &str,
+    scope: &str,
+    operator: &str,
+    now: DateTime<Utc>,
+) -> Result<()> {
+    if request.operation != operation || request.scope != scope {
+        return Err(denied(
+            request,
+            format!("approval {} was granted for `{}` ({}), not for this run ({})", request.id, request.operation, request.scope, scope),
+            None,
+        ));
+    }
+    if request.requested_by != operator {
+        return Err(denied(request, format!("approval {} was filed by {}", request.id, request.requested_by), None));
+    }
+    if request.used_at.is_some() {
+        return Err(denied(request, format!("approval {} has already been used", request.id), None));
+    }
+    if now >= request.expires_at {
+        return Err(denied(request, format!("approval {} expired at {}", request.id, request.expires_at), None));
+    }
+    match &request.approved_by {
+        None => Err(denied(request, format!("request {} has not been approved yet", request.id), Some(&request.id))),
+        Some(approver) if approver == &request.requested_by => {
+            Err(denied(request, format!("request {} was approved by its own requester", request.id), None))
+        }
+        Some(_) => Ok(()),
+    }
+}
+
+pub async fn ensure_approval_table(pool: &PgPool) -> Result<()> {
+    sqlx::raw_sql(
+        "CREATE SCHEMA IF NOT EXISTS erp_deploy;
+         CREATE TABLE IF NOT EXISTS erp_deploy.approvals (
+             id VARCHAR(32) PRIMARY KEY,
+             operation VARCHAR(100) NOT NULL,
+             scope TEXT NOT NULL,
+             requested_by VARCHAR(255) NOT NULL,
+             requested_at TIMESTAMPTZ NOT NULL,
+             expires_at TIMESTAMPTZ NOT NULL,
+             approved_by VARCHAR(255),
+             approved_at TIMESTAMPTZ,
+             used_at TIMESTAMPTZ
+         );",
+    )
+    .execute(pool)
+    .await
+    .db_step("create approvals table")?;
+    Ok(())
+}
+
+pub async fn file_request(pool: &PgPool, request: &ApprovalRequest) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO erp_deploy.approvals (id, operation, scope, requested_by, requested_at, expires_at) \
+         VALUES ($1, $2, $3, $4, $5, $6)",
+    )
+    .bind(&request.id)
+    .bind(&request.operation)
+    .bind(&request.scope)
+    .bind(&request.requested_by)
+    .bind(request.requested_at)
+    .bind(request.expires_at)
+    .execute(pool)
+    .await
+    .db_step("file approval request")?;
+    Ok(())
+}
+
+pub async fn load_request(pool: &PgPool, id: &str) -> Result<ApprovalRequest> {
+    let row = sqlx::query(
+        "SELECT id, operation, scope, requested_by, requested_at, expires_at, approved_by, approved_at, used_at \
+         FROM erp_deploy.approvals WHERE id = $1",
+    )
+    .bind(id.trim())
+    .fetch_optional(pool)
+    .await
+    .db_step("load approval request")?
+    .ok_or_else(|| DeployError::invalid_input(format!("No approval request {}", id)))?;
+
+    Ok(ApprovalRequest {
+        id: row.get("id"),
+        operation: row.get("operation"),
+        scope: row.get("scope"),
+        requested_by: row.get("requested_by"),
+        requested_at: row.get("requested_at"),
+        expires_at: row.get("expires_at"),
+        approved_by: row.get("approved_by"),
+        approved_at: row.get("approved_at"),
+        used_at: row.get("used_at"),
+    })
+}
+
+/// Record the approval of a second operator
+pub async fn approve_request(pool: &PgPool, id: &str, approver: &str) -> Result<ApprovalRequest> {
+    ensure_approval_table(pool).await?;
+    let mut request = load_request(pool, id).await?;
+    let now = Utc::now();
+    check_approver(&request, approver, now)?;
+
+    let updated = sqlx::query(
+        "UPDATE erp_deploy.approvals SET approved_by = $2, approved_at = $3 \
+         WHERE id = $1 AND approved_by IS NULL AND requested_by <> $2",
+    )
+    .bind(&request.id)
+    .bind(approver)
+    .bind(now)
+    .execute(pool)
+    .await
+    .db_step("approve request")?;
+    if updated.rows_affected() != 1 {
+        return Err(DeployError::invalid_input(format!("Request {} is already approved", request.id)));
+    }
+
+    request.approved_by = Some(approver.to_string());
+    request.approved_at = Some(now);
+    Ok(request)
+}
+
+/// Mark the approval used, as part of the operation's own transaction so a
+/// failed run leaves it usable
+pub async fn consume_approval(conn: &mut PgConnection, request: &ApprovalRequest) -> Result<()> {
+    let updated = sqlx::query("UPDATE erp_deploy.approvals SET used_at = NOW() WHERE id = $1 AND used_at IS NULL")
+        .bind(&request.id)
+        .execute(conn)
+        .await
+        .db_step("use approval")?;
+    if updated.rows_affected() != 1 {
+        return Err(denied(request, format!("approval {} has already been used", request.id), None));
+    }
+    Ok(())
+}
+
+/// The approval that lets this run proceed. Without `approval_id` a new
+/// request is filed and the run stops with the instructions for the approver.
+pub async fn require_approval(
+    pool: &PgPool,
+    operation: &str,
+    scope: &str,
+    approval_id: Option<&str>,
+) -> Result<ApprovalRequest> {
+    ensure_approval_table(pool).await?;
+    let operator = operator_identity();
+
+    let Some(id) = approval_id else {
+        let request = ApprovalRequest::new(operation, scope, &operator, Utc::now());
+        file_request(pool, &request).await?;
+        println!("{}", "🔐 Production requires a second operator's approval".yellow().bold());
+        println!("Request: {}", request.id.cyan());
+        println!("Scope: {}", request.scope);
+        println!("Expires: {}", request.expires_at.format("%Y-%m-%d %H:%M:%S UTC"));
+        return Err(denied(&request, "filed a new approval request", Some(&request.id)));
+    };
+
+    let request = load_request(pool, id).await?;
+    check_approval(&request, operation, scope, &operator, Utc::now())?;
+    println!(
+        "Approved by {} at {}",
+        request.approved_by.as_deref().unwrap_or_default().green(),
+        request.approved_at.map(|t| t.format("%Y-%m-%d %H:%M:%S UTC").to_string()).unwrap_or_default()
+    );
+    Ok(request)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn approved(request: &ApprovalRequest, approver: &str) -> ApprovalRequest {
+        ApprovalRequest {
+            approved_by: Some(approver.to_string()),
+            approved_at: Some(request.requested_at + Duration::minutes(5)),
+            ..request.clone()
+        }
+    }
+
+    #[test]
+    fn test_second_operator_must_approve() {
+        let now = Utc::now();
+        let request = ApprovalRequest::new("database reset", "tenant=acme", "alice@ops1", now);
+
+        let own = check_approver(&request, "alice@ops1", now).unwrap_err();
+        assert_eq!(own.code(), "APPROVAL_REQUIRED");
+        assert!(check_approver(&request, "bob@ops2", now).is_ok());
+
+        let pending = check_approval(&request, "database reset", "tenant=acme", "alice@ops1", now).unwrap_err();
+        assert!(pending.hint().contains(&format!("database approve {}", request.id)));
+
+        let request = approved(&request, "bob@ops2");
+        assert!(check_approver(&request, "carol@ops3", now).is_err(), "approved only once");
+        assert!(check_approval(&request, "database reset", "tenant=acme", "alice@ops1", now).is_ok());
+    }
+
+    #[test]
+    fn test_approval_is_bound_to_scope_requester_and_time() {
+        let now = Utc::now();
+        let request = approved(&ApprovalRequest::new("database reset", "tenant=acme", "alice@ops1", now), "bob@ops2");
+        let run = |scope: &str, operator: &str, at: DateTime<Utc>| check_approval(&request, "database reset", scope, operator, at);
+
+        assert!(run("all", "alice@ops1", now).is_err(), "different scope");
+        assert!(run("tenant=acme", "bob@ops2", now).is_err(), "approver cannot run it");
+        assert!(run("tenant=acme", "alice@ops1", request.expires_at).is_err(), "expired");
+
+        let used = ApprovalRequest { used_at: Some(now), ..request.clone() };
+        assert!(check_approval(&used, "database reset", "tenant=acme", "alice@ops1", now).is_err());
+
+        assert!(requires_approval("Production"));
+        assert!(!requires_approval("staging"));
+    }
+}
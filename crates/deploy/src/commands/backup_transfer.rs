@@ -0,0 +1,730 @@
+//! Chunked, resumable transfer of backup archives to remote storage
+//!
+//! An archive such as `erp_backup_20261017_020000.tar.xz` is stored in a
+//! remote directory of the same name:
+//!
+//! - `INCOMPLETE`: written before the first chunk and removed after the
+//!   manifest. While it exists the remote copy is listed as incomplete and
+//!   cannot be fetched.
+//! - `chunk-00000`, `chunk-00001`, ...: the archive in pieces of
+//!   `backup.remote.chunk_size_mb`
+//! - `manifest.json`: size and SHA-256 of the archive and of every chunk,
+//!   written last
+//!
+//! Upload progress is kept next to the archive in `<archive>.upload.json`.
+//! Uploading the same archive again skips the chunks recorded there that the
+//! store still holds, so a dropped connection costs at most the chunk that
+//! was in flight. A fetch checks every chunk and then the whole archive
+//! against the manifest before the archive appears under its final name.
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use super::remote_storage::ObjectStorage;
+use crate::error::{DeployError, IoResultExt, Result};
+
+pub const MANIFEST: &str = "manifest.json";
+pub const INCOMPLETE_MARKER: &str = "INCOMPLETE";
+
+fn chunk_name(index: u32) -> String {
+    format!("chunk-{:05}", index)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkInfo {
+    pub index: u32,
+    pub size: u64,
+    pub sha256: String,
+}
+
+/// Description of a complete remote copy
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoteManifest {
+    pub backup: String,
+    pub size: u64,
+    pub sha256: String,
+    pub chunk_size: u64,
+    pub chunks: Vec<ChunkInfo>,
+    /// When the archive was created
+    pub created_at: DateTime<Utc>,
+    pub uploaded_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IncompleteMarker {
+    backup: String,
+    sha256: String,
+    created_at: DateTime<Utc>,
+    started_at: DateTime<Utc>,
+}
+
+/// Upload progress of one archive
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct UploadState {
+    sha256: String,
+    chunk_size: u64,
+    uploaded: BTreeSet<u32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UploadSummary {
+    pub backup: String,
+    pub chunks: usize,
+    /// Chunks sent by this run
+    pub uploaded: usize,
+    /// Chunks an earlier, interrupted run had sent already
+    pub resumed: usize,
+    pub bytes_sent: u64,
+    /// The store held a complete copy of this archive before the run
+    pub already_complete: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FetchSummary {
+    pub backup: String,
+    pub path: PathBuf,
+    pub chunks: usize,
+    pub size: u64,
+}
+
+/// A backup as the remote store holds it
+#[derive(Debug, Clone, Serialize)]
+pub struct RemoteBackup {
+    pub name: String,
+    pub complete: bool,
+    /// Archive size when complete, bytes uploaded so far otherwise
+    pub size: u64,
+    /// `None` for leftovers that have neither manifest nor marker
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+/// Name of the remote copy of `archive`: its file name
+pub fn backup_name(archive: &Path) -> Result<String> {
+    let name = archive
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .ok_or_else(|| DeployError::invalid_input(format!("{} is not a backup archive", archive.display())))?;
+    check_backup_name(&name)?;
+    Ok(name)
+}
+
+fn check_backup_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && !name.starts_with('.')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'));
+    if !valid {
+        return Err(DeployError::invalid_input(format!("Invalid backup name: {}", name)));
+    }
+    Ok(())
+}
+
+/// Where the progress of uploading `archive` is kept
+pub fn upload_state_path(archive: &Path) -> PathBuf {
+    let mut name = archive.file_name().unwrap_or_default().to_os_string();
+    name.push(".upload.json");
+    archive.with_file_name(name)
+}
+
+/// Size and checksums of an archive split into chunks of `chunk_size`
+struct ArchivePlan {
+    size: u64,
+    sha256: String,
+    chunks: Vec<ChunkInfo>,
+}
+
+fn plan_archive(archive: &Path, chunk_size: u64) -> Result<ArchivePlan> {
+    const STEP: &str = "checksum backup archive";
+    let mut file = fs::File::open(archive).io_step(archive, STEP)?;
+    let mut whole = Sha256::new();
+    let mut buffer = vec![0u8; chunk_size as usize];
+    let mut plan = ArchivePlan {
+        size: 0,
+        sha256: String::new(),
+        chunks: Vec::new(),
+    };
+
+    loop {
+        let read = read_full(&mut file, &mut buffer).io_step(archive, STEP)?;
+        if read == 0 {
+            break;
+        }
+        whole.update(&buffer[..read]);
+        plan.chunks.push(ChunkInfo {
+            index: plan.chunks.len() as u32,
+            size: read as u64,
+            sha256: hex::encode(Sha256::digest(&buffer[..read])),
+        });
+        plan.size += read as u64;
+        if read < buffer.len() {
+            break;
+        }
+    }
+    plan.sha256 = hex::encode(whole.finalize());
+    Ok(plan)
+}
+
+/// Fill `buffer` unless the file ends first; returns the bytes read
+fn read_full(file: &mut fs::File, buffer: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match file.read(&mut buffer[filled..])? {
+            0 => break,
+            read => filled += read,
+        }
+    }
+    Ok(filled)
+}
+
+fn read_chunk(file: &mut fs::File, archive: &Path, chunk: &ChunkInfo, chunk_size: u64) -> Result<Vec<u8>> {
+    const STEP: &str = "read backup archive";
+    let mut body = vec![0u8; chunk.size as usize];
+    file.seek(SeekFrom::Start(u64::from(chunk.index) * chunk_size))
+        .io_step(archive, STEP)?;
+    file.read_exact(&mut body).io_step(archive, STEP)?;
+    Ok(body)
+}
+
+fn load_state(path: &Path) -> Result<Option<UploadState>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(path).io_step(path, "read upload progress")?;
+    // Unreadable progress only means starting over
+    Ok(serde_json::from_str(&content).ok())
+}
+
+fn save_state(path: &Path, state: &UploadState) -> Result<()> {
+    let partial = path.with_extension("json.partial");
+    fs::write(&partial, serde_json::to_vec(state)?).io_step(&partial, "save upload progress")?;
+    fs::rename(&partial, path).io_step(path, "save upload progress")
+}
+
+async fn read_manifest(storage: &dyn ObjectStorage, backup: &str) -> Result<Option<RemoteManifest>> {
+    match storage.get(&format!("{}/{}", backup, MANIFEST)).await? {
+        Some(body) => serde_json::from_slice(&body).map(Some).map_err(|e| DeployError::RemoteStorage {
+            target: storage.describe(),
+            step: "read remote backup manifest".to_string(),
+            message: format!("manifest of {} is unreadable", backup),
+            source: Some(Box::new(e)),
+        }),
+        None => Ok(None),
+    }
+}
+
+/// Upload `archive` in chunks of `chunk_size` bytes, resuming an interrupted
+/// upload of the same archive
+pub async fn upload_archive(storage: &dyn ObjectStorage, archive: &Path, chunk_size: u64) -> Result<UploadSummary> {
+    let backup = backup_name(archive)?;
+    let chunk_size = chunk_size.max(1);
+    let plan = plan_archive(archive, chunk_size)?;
+    let state_path = upload_state_path(archive);
+    let marker_key = format!("{}/{}", backup, INCOMPLETE_MARKER);
+    let mut summary = UploadSummary {
+        backup: backup.clone(),
+        chunks: plan.chunks.len(),
+        uploaded: 0,
+        resumed: 0,
+        bytes_sent: 0,
+        already_complete: false,
+    };
+
+    if let Some(manifest) = read_manifest(storage, &backup).await? {
+        if manifest.sha256 != plan.sha256 {
+            return Err(DeployError::remote_storage(
+                storage.describe(),
+                "upload backup",
+                format!("a different archive is already stored as {}", backup),
+            ));
+        }
+        // The manifest is written before the marker is removed
+        storage.delete(&marker_key).await?;
+        let _ = fs::remove_file(&state_path);
+        summary.already_complete = true;
+        return Ok(summary);
+    }
+
+    let mut state = match load_state(&state_path)? {
+        Some(state) if state.sha256 == plan.sha256 && state.chunk_size == chunk_size => state,
+        _ => UploadState {
+            sha256: plan.sha256.clone(),
+            chunk_size,
+            uploaded: BTreeSet::new(),
+        },
+    };
+    let created_at = fs::metadata(archive)
+        .and_then(|metadata| metadata.modified())
+        .map(DateTime::<Utc>::from)
+        .unwrap_or_else(|_| Utc::now());
+
+    let marker = IncompleteMarker {
+        backup: backup.clone(),
+        sha256: plan.sha256.clone(),
+        created_at,
+        started_at: Utc::now(),
+    };
+    storage.put(&marker_key, serde_json::to_vec_pretty(&marker)?).await?;
+
+    let stored: HashMap<String, u64> = storage
+        .list(&backup)
+        .await?
+        .into_iter()
+        .filter(|entry| !entry.is_dir)
+        .map(|entry| (entry.name, entry.size))
+        .collect();
+    let mut file = fs::File::open(archive).io_step(archive, "read backup archive")?;
+
+    for chunk in &plan.chunks {
+        let name = chunk_name(chunk.index);
+        if state.uploaded.contains(&chunk.index) && stored.get(&name) == Some(&chunk.size) {
+            summary.resumed += 1;
+            continue;
+        }
+
+        let body = read_chunk(&mut file, archive, chunk, chunk_size)?;
+        if let Err(e) = storage.put(&format!("{}/{}", backup, name), body).await {
+            return Err(DeployError::RemoteStorage {
+                target: storage.describe(),
+                step: "upload backup".to_string(),
+                message: format!(
+                    "upload of {} stopped after {} of {} chunks and is marked incomplete; run `erp-deploy backup upload {}` to resume",
+                    backup,
+                    state.uploaded.len(),
+                    plan.chunks.len(),
+                    archive.display()
+                ),
+                source: Some(Box::new(e)),
+            });
+        }
+        state.uploaded.insert(chunk.index);
+        save_state(&state_path, &state)?;
+        summary.uploaded += 1;
+        summary.bytes_sent += chunk.size;
+    }
+
+    let manifest = RemoteManifest {
+        backup: backup.clone(),
+        size: plan.size,
+        sha256: plan.sha256,
+        chunk_size,
+        chunks: plan.chunks,
+        created_at,
+        uploaded_at: Utc::now(),
+    };
+    storage
+        .put(&format!("{}/{}", backup, MANIFEST), serde_json::to_vec_pretty(&manifest)?)
+        .await?;
+    storage.delete(&marker_key).await?;
+    let _ = fs::remove_file(&state_path);
+    Ok(summary)
+}
+
+/// Download a complete remote backup into `directory`, verified against its
+/// manifest; nothing is left under the final name unless every check passed
+pub async fn fetch_archive(storage: &dyn ObjectStorage, backup: &str, directory: &Path) -> Result<FetchSummary> {
+    check_backup_name(backup)?;
+    let entries = storage.list(backup).await?;
+    if entries.iter().any(|entry| entry.name == INCOMPLETE_MARKER) {
+        return Err(DeployError::remote_storage(
+            storage.describe(),
+            "fetch backup",
+            format!("the upload of {} did not finish; resume it on the host that created it", backup),
+        ));
+    }
+    let manifest = read_manifest(storage, backup).await?.ok_or_else(|| {
+        DeployError::remote_storage(storage.describe(), "fetch backup", format!("no complete backup named {}", backup))
+    })?;
+
+    let target = directory.join(backup);
+    if target.exists() {
+        return Err(DeployError::invalid_input(format!("{} exists already", target.display())));
+    }
+    fs::create_dir_all(directory).io_step(directory, "fetch backup")?;
+    let partial = directory.join(format!("{}.partial", backup));
+
+    match download_chunks(storage, &manifest, &partial).await {
+        Ok(()) => {
+            fs::rename(&partial, &target).io_step(&target, "fetch backup")?;
+            Ok(FetchSummary {
+                backup: backup.to_string(),
+                path: target,
+                chunks: manifest.chunks.len(),
+                size: manifest.size,
+            })
+        }
+        Err(e) => {
+            let _ = fs::remove_file(&partial);
+            Err(e)
+        }
+    }
+}
+
+async fn download_chunks(storage: &dyn ObjectStorage, manifest: &RemoteManifest, partial: &Path) -> Result<()> {
+    const STEP: &str = "verify fetched backup";
+    let corrupt = |message: String| DeployError::remote_storage(storage.describe(), STEP, message);
+    let mut file = fs::File::create(partial).io_step(partial, "fetch backup")?;
+    let mut whole = Sha256::new();
+    let mut size = 0u64;
+
+    for chunk in &manifest.chunks {
+        let body = storage
+            .get(&format!("{}/{}", manifest.backup, chunk_name(chunk.index)))
+            .await?
+            .ok_or_else(|| corrupt(format!("chunk {} of {} is missing", chunk.index, manifest.backup)))?;
+        if body.len() as u64 != chunk.size || hex::encode(Sha256::digest(&body)) != chunk.sha256 {
+            return Err(corrupt(format!(
+                "chunk {} of {} does not match its checksum",
+                chunk.index, manifest.backup
+            )));
+        }
+        whole.update(&body);
+        size += chunk.size;
+        file.write_all(&body).io_step(partial, "fetch backup")?;
+    }
+    file.sync_all().io_step(partial, "fetch backup")?;
+
+    if size != manifest.size || hex::encode(whole.finalize()) != manifest.sha256 {
+        return Err(corrupt(format!("{} does not match its checksum", manifest.backup)));
+    }
+    Ok(())
+}
+
+/// Every backup the remote store holds, complete or not, newest first
+pub async fn remote_inventory(storage: &dyn ObjectStorage) -> Result<Vec<RemoteBackup>> {
+    let mut backups = Vec::new();
+    for dir in storage.list("").await?.into_iter().filter(|entry| entry.is_dir) {
+        let entries = storage.list(&dir.name).await?;
+        let uploaded: u64 = entries
+            .iter()
+            .filter(|entry| entry.name.starts_with("chunk-"))
+            .map(|entry| entry.size)
+            .sum();
+        let has = |name: &str| entries.iter().any(|entry| entry.name == name);
+
+        let backup = if has(INCOMPLETE_MARKER) {
+            let marker: Option<IncompleteMarker> = storage
+                .get(&format!("{}/{}", dir.name, INCOMPLETE_MARKER))
+                .await?
+                .and_then(|body| serde_json::from_slice(&body).ok());
+            RemoteBackup {
+                name: dir.name,
+                complete: false,
+                size: uploaded,
+                created_at: marker.map(|marker| marker.created_at),
+            }
+        } else if has(MANIFEST) {
+            match read_manifest(storage, &dir.name).await? {
+                Some(manifest) => RemoteBackup {
+                    name: dir.name,
+                    complete: true,
+                    size: manifest.size,
+                    created_at: Some(manifest.created_at),
+                },
+                None => continue,
+            }
+        } else if uploaded > 0 {
+            RemoteBackup {
+                name: dir.name,
+                complete: false,
+                size: uploaded,
+                created_at: None,
+            }
+        } else {
+            // Not a backup
+            continue;
+        };
+        backups.push(backup);
+    }
+
+    backups.sort_by(|a, b| b.created_at.cmp(&a.created_at).then_with(|| a.name.cmp(&b.name)));
+    Ok(backups)
+}
+
+/// Delete remote backups, complete or not, created more than
+/// `retention_days` before `now`; returns those deleted, or that would be
+/// with `dry_run`
+pub async fn cleanup_remote(
+    storage: &dyn ObjectStorage,
+    retention_days: u32,
+    dry_run: bool,
+    now: DateTime<Utc>,
+) -> Result<Vec<RemoteBackup>> {
+    let cutoff = now - Duration::days(i64::from(retention_days));
+    let expired: Vec<RemoteBackup> = remote_inventory(storage)
+        .await?
+        .into_iter()
+        .filter(|backup| backup.created_at.is_some_and(|created_at| created_at < cutoff))
+        .collect();
+
+    if !dry_run {
+        for backup in &expired {
+            // Marker first, manifest last: an interrupted delete never looks complete
+            for entry in storage.list(&backup.name).await? {
+                storage.delete(&format!("{}/{}", backup.name, entry.name)).await?;
+            }
+            storage.remove_dir(&backup.name).await?;
+        }
+    }
+    Ok(expired)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::remote_storage::StoredEntry;
+    use async_trait::async_trait;
+    use std::collections::BTreeMap;
+    use std::sync::Mutex;
+
+    const CHUNK: u64 = 1024;
+
+    /// Objects in memory
+    #[derive(Default)]
+    struct MemoryStorage {
+        objects: Mutex<BTreeMap<String, Vec<u8>>>,
+    }
+
+    #[async_trait]
+    impl ObjectStorage for MemoryStorage {
+        fn describe(&self) -> String {
+            "memory://backups".to_string()
+        }
+
+        async fn put(&self, key: &str, body: Vec<u8>) -> Result<()> {
+            self.objects.lock().unwrap().insert(key.to_string(), body);
+            Ok(())
+        }
+
+        async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+            Ok(self.objects.lock().unwrap().get(key).cloned())
+        }
+
+        async fn list(&self, dir: &str) -> Result<Vec<StoredEntry>> {
+            let prefix = if dir.is_empty() { String::new() } else { format!("{}/", dir) };
+            let mut entries: Vec<StoredEntry> = Vec::new();
+            for (key, body) in self.objects.lock().unwrap().iter() {
+                let Some(rest) = key.strip_prefix(&prefix) else {
+                    continue;
+                };
+                match rest.split_once('/') {
+                    Some((child, _)) => {
+                        if !entries.iter().any(|entry| entry.name == child) {
+                            entries.push(StoredEntry { name: child.to_string(), size: 0, is_dir: true });
+                        }
+                    }
+                    None => entries.push(StoredEntry { name: rest.to_string(), size: body.len() as u64, is_dir: false }),
+                }
+            }
+            Ok(entries)
+        }
+
+        async fn delete(&self, key: &str) -> Result<()> {
+            self.objects.lock().unwrap().remove(key);
+            Ok(())
+        }
+    }
+
+    /// Passes calls through until `chunks_left` chunk uploads have
+    /// succeeded, then fails every upload as a dropped connection would
+    pub(super) struct Interrupting<'a> {
+        pub inner: &'a dyn ObjectStorage,
+        pub chunks_left: Mutex<usize>,
+        pub chunk_puts: Mutex<usize>,
+    }
+
+    impl<'a> Interrupting<'a> {
+        pub fn new(inner: &'a dyn ObjectStorage, chunks_left: usize) -> Self {
+            Self {
+                inner,
+                chunks_left: Mutex::new(chunks_left),
+                chunk_puts: Mutex::new(0),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl ObjectStorage for Interrupting<'_> {
+        fn describe(&self) -> String {
+            self.inner.describe()
+        }
+
+        async fn put(&self, key: &str, body: Vec<u8>) -> Result<()> {
+            if key.contains("/chunk-") {
+                let mut left = self.chunks_left.lock().unwrap();
+                if *left == 0 {
+                    return Err(DeployError::remote_storage(self.describe(), "upload backup chunk", "connection reset"));
+                }
+                *left -= 1;
+                *self.chunk_puts.lock().unwrap() += 1;
+            }
+            self.inner.put(key, body).await
+        }
+
+        async fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+            self.inner.get(key).await
+        }
+
+        async fn list(&self, dir: &str) -> Result<Vec<StoredEntry>> {
+            self.inner.list(dir).await
+        }
+
+        async fn delete(&self, key: &str) -> Result<()> {
+            self.inner.delete(key).await
+        }
+
+        async fn remove_dir(&self, dir: &str) -> Result<()> {
+            self.inner.remove_dir(dir).await
+        }
+    }
+
+    /// An archive of `size` pseudo-random bytes
+    pub(super) fn archive(dir: &Path, name: &str, size: usize) -> PathBuf {
+        let path = dir.join(name);
+        let bytes: Vec<u8> = (0..size).map(|i| (i * 31 % 251) as u8).collect();
+        fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    /// Interrupt an upload after four chunks, check it is visibly
+    /// incomplete, resume it and fetch it back
+    pub(super) async fn interrupted_upload_resumes(storage: &dyn ObjectStorage, name: &str) {
+        let local = tempfile::tempdir().unwrap();
+        let archive = archive(local.path(), name, 10 * CHUNK as usize + 100);
+
+        let flaky = Interrupting::new(storage, 4);
+        let error = upload_archive(&flaky, &archive, CHUNK).await.unwrap_err();
+        assert!(error.to_string().contains("stopped after 4 of 11 chunks"), "{}", error);
+        assert!(upload_state_path(&archive).exists());
+
+        let listed = remote_inventory(storage).await.unwrap();
+        let listed = listed.iter().find(|backup| backup.name == name).unwrap();
+        assert!(!listed.complete);
+        assert_eq!(listed.size, 4 * CHUNK);
+        let refused = fetch_archive(storage, name, &local.path().join("fetched")).await.unwrap_err();
+        assert!(refused.to_string().contains("did not finish"), "{}", refused);
+
+        let resumed = Interrupting::new(storage, usize::MAX);
+        let summary = upload_archive(&resumed, &archive, CHUNK).await.unwrap();
+        assert_eq!((summary.resumed, summary.uploaded), (4, 7));
+        assert_eq!(*resumed.chunk_puts.lock().unwrap(), 7);
+        assert!(!upload_state_path(&archive).exists());
+
+        let listed = remote_inventory(storage).await.unwrap();
+        let listed = listed.iter().find(|backup| backup.name == name).unwrap();
+        assert!(listed.complete);
+        assert_eq!(listed.size, 10 * CHUNK + 100);
+
+        let fetched = fetch_archive(storage, name, &local.path().join("fetched")).await.unwrap();
+        assert_eq!(fs::read(&fetched.path).unwrap(), fs::read(&archive).unwrap());
+
+        // A second upload of the same archive has nothing left to do
+        assert!(upload_archive(storage, &archive, CHUNK).await.unwrap().already_complete);
+    }
+
+    #[tokio::test]
+    async fn test_interrupted_upload_resumes_where_it_stopped() {
+        interrupted_upload_resumes(&MemoryStorage::default(), "erp_backup_20261017_020000.tar.xz").await;
+    }
+
+    #[tokio::test]
+    async fn test_fetch_verifies_every_chunk_before_the_archive_appears() {
+        let storage = MemoryStorage::default();
+        let local = tempfile::tempdir().unwrap();
+        let archive = archive(local.path(), "erp_backup_1.tar.xz", 3 * CHUNK as usize);
+        upload_archive(&storage, &archive, CHUNK).await.unwrap();
+
+        // Same size, one byte flipped
+        let key = "erp_backup_1.tar.xz/chunk-00001".to_string();
+        storage.objects.lock().unwrap().get_mut(&key).unwrap()[10] ^= 0xff;
+
+        let target = local.path().join("restore");
+        let error = fetch_archive(&storage, "erp_backup_1.tar.xz", &target).await.unwrap_err();
+        assert!(error.to_string().contains("chunk 1 of erp_backup_1.tar.xz does not match"), "{}", error);
+        assert_eq!(fs::read_dir(&target).unwrap().count(), 0);
+
+        storage.objects.lock().unwrap().remove(&key);
+        let error = fetch_archive(&storage, "erp_backup_1.tar.xz", &target).await.unwrap_err();
+        assert!(error.to_string().contains("is missing"), "{}", error);
+        assert!(fetch_archive(&storage, "../etc", &target).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_remote_cleanup_follows_its_own_retention() {
+        let storage = MemoryStorage::default();
+        let local = tempfile::tempdir().unwrap();
+        for name in ["erp_backup_old.tar.xz", "erp_backup_new.tar.xz"] {
+            upload_archive(&storage, &archive(local.path(), name, 100), CHUNK).await.unwrap();
+        }
+        // An abandoned upload of an old archive
+        let abandoned = archive(local.path(), "erp_backup_abandoned.tar.xz", 3 * CHUNK as usize);
+        assert!(upload_archive(&Interrupting::new(&storage, 1), &abandoned, CHUNK).await.is_err());
+
+        // Age the old backups
+        let aged = Utc::now() - Duration::days(100);
+        {
+            let mut objects = storage.objects.lock().unwrap();
+            for key in ["erp_backup_old.tar.xz/manifest.json", "erp_backup_abandoned.tar.xz/INCOMPLETE"] {
+                let mut document: serde_json::Value = serde_json::from_slice(&objects[key]).unwrap();
+                document["created_at"] = serde_json::json!(aged);
+                objects.insert(key.to_string(), serde_json::to_vec(&document).unwrap());
+            }
+        }
+
+        let now = Utc::now();
+        let planned = cleanup_remote(&storage, 90, true, now).await.unwrap();
+        assert_eq!(planned.len(), 2);
+        assert_eq!(remote_inventory(&storage).await.unwrap().len(), 3);
+
+        let deleted = cleanup_remote(&storage, 90, false, now).await.unwrap();
+        let mut names: Vec<&str> = deleted.iter().map(|backup| backup.name.as_str()).collect();
+        names.sort();
+        assert_eq!(names, ["erp_backup_abandoned.tar.xz", "erp_backup_old.tar.xz"]);
+        let left = remote_inventory(&storage).await.unwrap();
+        assert_eq!(left.len(), 1);
+        assert_eq!(left[0].name, "erp_backup_new.tar.xz");
+        assert!(storage.objects.lock().unwrap().keys().all(|key| key.starts_with("erp_backup_new")));
+
+        // The local retention does not apply: a 100 day old copy survives 120 days of remote retention
+        assert!(cleanup_remote(&storage, 120, false, now + Duration::days(100)).await.unwrap().is_empty());
+    }
+
+    /// Against an S3-compatible store, e.g.
+    /// `docker run -p 9000:9000 minio/minio server /data` with a bucket
+    /// `erp-backup-test`
+    #[tokio::test]
+    #[ignore = "requires MinIO"]
+    async fn test_s3_upload_resumes_after_interrupt() {
+        use crate::commands::remote_storage::S3Storage;
+
+        let env = |name: &str, default: &str| std::env::var(name).unwrap_or_else(|_| default.to_string());
+        let storage = S3Storage::new(
+            &env("ERP_TEST_S3_ENDPOINT", "http://localhost:9000"),
+            &env("ERP_TEST_S3_BUCKET", "erp-backup-test"),
+            "us-east-1",
+            &format!("test-{}", uuid::Uuid::new_v4()),
+            env("ERP_TEST_S3_ACCESS_KEY", "minioadmin"),
+            env("ERP_TEST_S3_SECRET_KEY", "minioadmin"),
+            true,
+        )
+        .unwrap();
+
+        interrupted_upload_resumes(&storage, "erp_backup_20261017_020000.tar.xz").await;
+
+        // Fetching a corrupted chunk fails before anything is restored
+        storage
+            .put("erp_backup_20261017_020000.tar.xz/chunk-00002", vec![0; CHUNK as usize])
+            .await
+            .unwrap();
+        let local = tempfile::tempdir().unwrap();
+        assert!(fetch_archive(&storage, "erp_backup_20261017_020000.tar.xz", local.path()).await.is_err());
+        assert_eq!(fs::read_dir(local.path()).unwrap().count(), 0);
+
+        let deleted = cleanup_remote(&storage, 1, false, Utc::now() + Duration::days(2)).await.unwrap();
+        assert_eq!(deleted.len(), 1);
+        assert!(storage.list("").await.unwrap().is_empty());
+    }
+}
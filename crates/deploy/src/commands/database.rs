@@ -1,11 +1,24 @@
 //! Database management command implementations
 
-use anyhow::{anyhow, Result};
+use chrono::{Duration, Utc};
 use colored::*;
-use sqlx::PgPool;
+use erp_core::{migration_audit, numbering};
+use sqlx::{PgPool, Row};
+use uuid::Uuid;
+use std::io;
 use std::path::Path;
+use std::process::Stdio;
 use tokio::process::Command;
 
+use super::approval::{approve_request, operator_identity, require_approval, requires_approval};
+use super::db_reset::{
+    migration_source, parse_preserve, reset_scope, run_reset, ResetOptions, BUNDLED_MIGRATIONS, TENANT_SCHEMA_TEMPLATE,
+};
+use super::schema_backup::{
+    dump_file_name, file_checksum, find_tenant, migration_version, read_manifest, shared_tables, tenant_dump_selection,
+    write_manifest, DumpCompression, DumpManifest,
+};
+use crate::error::{DbResultExt, DeployError, IoResultExt, ProcessResultExt, Result};
 use crate::{DatabaseCommands, config::Config};
 
 pub async fn execute_database_command(
@@ -15,35 +28,64 @@ pub async fn execute_database_command(
 ) -> Result<()> {
     let db_url = database_url
         .or(config.database_url.as_deref())
-        .ok_or_else(|| anyhow!("Database URL not provided"))?;
+        .ok_or_else(|| DeployError::config(None, "Database URL not provided"))?;
 
     match cmd {
         DatabaseCommands::Migrate { dry_run, tenant, target } => {
-            migrate_database(db_url, tenant.as_deref(), target.as_deref(), dry_run).await
+            migrate_database(db_url, tenant.as_deref(), target.as_deref(), dry_run, &config.default_environment).await
         }
-        DatabaseCommands::Backup { name, output } => {
-            backup_database(db_url, Some(&name), output.as_deref(), "gzip").await
+        DatabaseCommands::Backup { name, output, tenant, compression } => {
+            backup_database(db_url, &name, tenant.as_deref(), output.as_deref(), &compression).await
         }
         DatabaseCommands::Restore { backup, force } => {
-            restore_database(db_url, &backup, "default", force).await
+            restore_database(db_url, &backup, force).await
         }
         DatabaseCommands::Check { detailed } => {
             check_database(db_url, detailed, detailed, detailed).await
         }
-        DatabaseCommands::Reset { force, tenant } => {
-            reset_database(db_url, tenant.as_deref(), force).await
+        DatabaseCommands::Reset { force, tenant, preserve, environment, approval } => {
+            let environment = environment.unwrap_or_else(|| config.default_environment.clone());
+            let preserve = preserve.as_deref().map(parse_preserve).transpose()?.unwrap_or_default();
+            reset_database(db_url, tenant, preserve, &environment, approval.as_deref(), force).await
         }
-        DatabaseCommands::Status => {
-            status_database(db_url).await
+        DatabaseCommands::Approve { request } => {
+            approve(db_url, &request).await
+        }
+        DatabaseCommands::Status { history, slow_threshold_secs, limit } => {
+            status_database(db_url).await?;
+            if history {
+                migration_history(db_url, limit, std::time::Duration::from_secs(slow_threshold_secs)).await?;
+            }
+            Ok(())
+        }
+        DatabaseCommands::SequenceGaps { tenant, stale_hours } => {
+            sequence_gaps(db_url, tenant.as_deref(), stale_hours).await
         }
     }
 }
 
+/// Open a pool, naming the database host if the server can't be reached
+pub async fn connect(database_url: &str) -> Result<PgPool> {
+    PgPool::connect(database_url)
+        .await
+        .connecting_to(database_url, "connect to database")
+}
+
+/// Parse the database URL, reporting a malformed one as a configuration error
+pub fn parse_database_url(database_url: &str) -> Result<url::Url> {
+    url::Url::parse(database_url).map_err(|e| DeployError::Config {
+        path: None,
+        message: "invalid database URL".to_string(),
+        source: Some(Box::new(e)),
+    })
+}
+
 async fn migrate_database(
     database_url: &str,
     tenant: Option<&str>,
     _target: Option<&str>,
     dry_run: bool,
+    environment: &str,
 ) -> Result<()> {
     println!("{}", "🔄 Running database migrations...".blue().bold());
 
@@ -57,46 +99,47 @@ async fn migrate_database(
         println!("Target: {}", "All schemas".cyan());
     }
 
-    let pool = PgPool::connect(database_url).await?;
+    let pool = connect(database_url).await?;
 
     // Check current migration status
-    let migration_table_exists = sqlx::query!(
-        "SELECT EXISTS (
-            SELECT FROM information_schema.tables
-            WHERE table_schema = 'public'
-            AND table_name = '_sqlx_migrations'
-        ) as exists"
-    )
-    .fetch_one(&pool)
-    .await?;
-
-    if !migration_table_exists.exists.unwrap_or(false) {
-        println!("Creating migration tracking table...");
-        if !dry_run {
-            // Note: Migration directory should be created and populated with .sql files
-            // For now, just create the basic migration table manually
-            sqlx::query!(
-                "CREATE TABLE IF NOT EXISTS _sqlx_migrations (
-                    version BIGINT PRIMARY KEY,
-                    description TEXT NOT NULL,
-                    installed_on TIMESTAMPTZ NOT NULL DEFAULT NOW(),
-                    success BOOLEAN NOT NULL,
-                    checksum BYTEA NOT NULL,
-                    execution_time BIGINT NOT NULL
-                )"
-            )
-            .execute(&pool)
-            .await?;
-            println!("Migration tracking table created");
-        }
-    }
+    let migration_table_exists: bool =
+        sqlx::query_scalar("SELECT to_regclass('public._sqlx_migrations') IS NOT NULL")
+            .fetch_one(&pool)
+            .await
+            .migration_step(tenant, "check migration table")?;
+    let applied: Vec<i64> = if migration_table_exists {
+        sqlx::query_scalar("SELECT version FROM public._sqlx_migrations WHERE success")
+            .fetch_all(&pool)
+            .await
+            .migration_step(tenant, "read applied migrations")?
+    } else {
+        Vec::new()
+    };
 
     // List pending migrations
     println!("Checking for pending migrations...");
+    let pending: Vec<_> = BUNDLED_MIGRATIONS
+        .iter()
+        .filter(|m| !m.migration_type.is_down_migration() && !applied.contains(&m.version))
+        .collect();
+    if pending.is_empty() {
+        println!("No pending migrations");
+    }
+    for migration in &pending {
+        println!("  {:>4} {}", migration.version, migration.description);
+    }
 
     if dry_run {
         println!("{}", "✅ Dry run completed".green());
-    } else {
+    } else if !pending.is_empty() {
+        let mut conn = pool.acquire().await.migration_step(tenant, "acquire connection")?;
+        migration_audit::run_audited(&mut conn, &BUNDLED_MIGRATIONS, &migration_source(environment))
+            .await
+            .map_err(|e| DeployError::MigrationFailed {
+                tenant: tenant.map(str::to_string),
+                step: "run migrations".to_string(),
+                source: Box::new(e),
+            })?;
         println!("{}", "✅ Migrations completed successfully".green());
     }
 
@@ -106,53 +149,65 @@ async fn migrate_database(
 
 async fn backup_database(
     database_url: &str,
+    name: &str,
     tenant: Option<&str>,
     output: Option<&str>,
     compression: &str,
 ) -> Result<()> {
     println!("{}", "💾 Creating database backup...".blue().bold());
 
+    let compression = DumpCompression::parse(compression)?;
+    let created_at = Utc::now();
+    let output_dir = Path::new(output.unwrap_or("."));
+    let dump_path = output_dir.join(dump_file_name(name, compression, created_at)?);
+    // pg_dump writes the dump uncompressed; the compression tool adds its extension
+    let raw_path = output_dir.join(dump_file_name(name, DumpCompression::None, created_at)?);
+
     // Parse database URL to extract connection details
-    let url = url::Url::parse(database_url)?;
+    let url = parse_database_url(database_url)?;
     let host = url.host_str().unwrap_or("localhost");
     let port = url.port().unwrap_or(5432);
     let username = url.username();
     let password = url.password().unwrap_or("");
     let database = url.path().trim_start_matches('/');
 
-    let timestamp = chrono::Utc::now().format("%Y%m%d_%H%M%S");
-    let backup_name = tenant.unwrap_or("full_backup");
-    let backup_filename = format!("{}_{}.sql", backup_name, timestamp);
-
-    let output_path = output.unwrap_or(&backup_filename);
+    let pool = connect(database_url).await?;
+    let tenant = match tenant {
+        Some(tenant) => Some(find_tenant(&pool, tenant).await?),
+        None => None,
+    };
+    let shared = match &tenant {
+        Some(tenant) => shared_tables(&pool, &tenant.schema).await?,
+        None => Vec::new(),
+    };
+    let migration_version = migration_version(&pool).await?;
+    pool.close().await;
 
     println!("Host: {}", host.yellow());
     println!("Database: {}", database.yellow());
-    println!("Backup name: {}", backup_name.yellow());
-    println!("Output: {}", output_path.yellow());
-    println!("Compression: {}", compression.yellow());
+    match &tenant {
+        Some(tenant) => println!("Tenant schema: {}", tenant.schema.yellow()),
+        None => println!("Tenant schema: {}", "all".yellow()),
+    }
+    if !shared.is_empty() {
+        println!("Shared tables: {}", shared.join(", ").yellow());
+    }
+    println!("Output: {}", dump_path.display().to_string().yellow());
+    println!("Compression: {}", compression.tool().unwrap_or("none").yellow());
 
-    // Build pg_dump command
+    // Build pg_dump command; custom format, compressed afterwards by the chosen tool
     let mut cmd = Command::new("pg_dump");
     cmd.arg("--host").arg(host)
        .arg("--port").arg(port.to_string())
        .arg("--username").arg(username)
        .arg("--no-password")
-       .arg("--verbose");
-
-    // Set format based on compression
-    match compression {
-        "gzip" => cmd.arg("--format").arg("custom"),
-        "bzip2" => cmd.arg("--format").arg("custom"),
-        "none" => cmd.arg("--format").arg("plain"),
-        _ => cmd.arg("--format").arg("custom"),
-    };
-
-    cmd.arg("--file").arg(output_path);
+       .arg("--verbose")
+       .arg("--format").arg("custom")
+       .arg("--compress").arg("0")
+       .arg("--file").arg(&raw_path);
 
-    // Add schema filter if tenant is specified
-    if let Some(schema) = tenant {
-        cmd.arg("--schema").arg(schema);
+    if let Some(tenant) = &tenant {
+        cmd.args(tenant_dump_selection(&tenant.schema, &shared));
     }
 
     cmd.arg(database);
@@ -161,21 +216,35 @@ async fn backup_database(
     cmd.env("PGPASSWORD", password);
 
     println!("Running pg_dump...");
-    let output = cmd.output().await?;
-
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(anyhow!("Backup failed: {}", stderr));
+    cmd.output().await.checked("pg_dump", "back up database")?;
+
+    if let Some(tool) = compression.tool() {
+        println!("Compressing with {}...", tool);
+        Command::new(tool)
+            .arg("--force")
+            .arg(&raw_path)
+            .output()
+            .await
+            .checked(tool, "compress backup")?;
     }
 
-    // Apply compression if requested
-    match compression {
-        "gzip" => println!("Backup created with gzip compression"),
-        "bzip2" => println!("Backup created with bzip2 compression"),
-        "none" => println!("Backup created without compression"),
-        _ => println!("Backup created with default compression"),
+    let (size, sha256) = file_checksum(&dump_path)?;
+    let manifest = DumpManifest {
+        backup: dump_path.file_name().unwrap_or_default().to_string_lossy().into_owned(),
+        tenant_id: tenant.as_ref().map(|t| t.tenant_id),
+        schema_name: tenant.map(|t| t.schema),
+        shared_tables: shared,
+        migration_version,
+        compression,
+        size,
+        sha256,
+        created_at,
     };
+    let manifest_path = write_manifest(&dump_path, &manifest)?;
 
+    println!("Size: {} bytes", manifest.size);
+    println!("SHA-256: {}", manifest.sha256);
+    println!("Manifest: {}", manifest_path.display());
     println!("{}", "✅ Backup completed successfully".green().bold());
     Ok(())
 }
@@ -183,17 +252,45 @@ async fn backup_database(
 async fn restore_database(
     database_url: &str,
     backup_file: &str,
-    tenant: &str,
     force: bool,
 ) -> Result<()> {
     println!("{}", "🔄 Restoring database from backup...".blue().bold());
 
-    if !Path::new(backup_file).exists() {
-        return Err(anyhow!("Backup file not found: {}", backup_file));
+    let dump_path = Path::new(backup_file);
+    if !dump_path.exists() {
+        return Err(DeployError::from_io(
+            io::Error::new(io::ErrorKind::NotFound, "backup file not found"),
+            Some(dump_path),
+            "restore database",
+        ));
     }
 
+    // Everything is checked before anything is overwritten
+    let manifest = read_manifest(dump_path)?;
+    manifest.verify_dump(dump_path)?;
+
+    let pool = connect(database_url).await?;
+    manifest.check_target(migration_version(&pool).await?)?;
+    if let (Some(tenant_id), Some(schema)) = (manifest.tenant_id, &manifest.schema_name) {
+        let target = find_tenant(&pool, &tenant_id.to_string()).await?;
+        if &target.schema != schema {
+            return Err(DeployError::invalid_input(format!(
+                "Tenant {} uses schema {} in the target database, the backup holds {}",
+                tenant_id, target.schema, schema
+            )));
+        }
+    }
+    pool.close().await;
+
     println!("Backup file: {}", backup_file.yellow());
-    println!("Target tenant: {}", tenant.yellow());
+    println!("Taken: {}", manifest.created_at.format("%Y-%m-%d %H:%M:%S UTC"));
+    match &manifest.schema_name {
+        Some(schema) => println!("Target tenant schema: {}", schema.yellow()),
+        None => println!("Target: {}", "whole database".yellow()),
+    }
+    if let Some(version) = manifest.migration_version {
+        println!("Migration version: {}", version);
+    }
 
     if !force {
         use dialoguer::Confirm;
@@ -206,8 +303,29 @@ async fn restore_database(
         }
     }
 
+    // pg_restore reads the uncompressed custom format
+    let workdir = tempfile::tempdir().io_step(std::env::temp_dir(), "prepare restore")?;
+    let restore_path = match manifest.compression.tool() {
+        Some(tool) => {
+            let raw_path = workdir.path().join("restore.dump");
+            let raw = std::fs::File::create(&raw_path).io_step(&raw_path, "decompress backup")?;
+            // `output()` would capture stdout; the dump goes to the file instead
+            let decompress = Command::new(tool)
+                .arg("--decompress")
+                .arg("--stdout")
+                .arg(dump_path)
+                .stdout(raw)
+                .stderr(Stdio::piped())
+                .spawn()
+                .map_err(|e| DeployError::from_spawn(e, tool, "decompress backup"))?;
+            decompress.wait_with_output().await.checked(tool, "decompress backup")?;
+            raw_path
+        }
+        None => dump_path.to_path_buf(),
+    };
+
     // Parse database URL
-    let url = url::Url::parse(database_url)?;
+    let url = parse_database_url(database_url)?;
     let host = url.host_str().unwrap_or("localhost");
     let port = url.port().unwrap_or(5432);
     let username = url.username();
@@ -225,20 +343,17 @@ async fn restore_database(
        .arg("--clean")
        .arg("--if-exists");
 
-    // Add schema filter for tenant-specific restore
-    cmd.arg("--schema").arg(tenant);
+    // A tenant backup restores the tenant's schema, never the shared tables
+    if let Some(schema) = &manifest.schema_name {
+        cmd.arg("--schema").arg(schema);
+    }
 
-    cmd.arg(backup_file);
+    cmd.arg(&restore_path);
 
     cmd.env("PGPASSWORD", password);
 
     println!("Running pg_restore...");
-    let output = cmd.output().await?;
-
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(anyhow!("Restore failed: {}", stderr));
-    }
+    cmd.output().await.checked("pg_restore", "restore database")?;
 
     println!("{}", "✅ Restore completed successfully".green().bold());
     Ok(())
@@ -252,13 +367,14 @@ async fn check_database(
 ) -> Result<()> {
     println!("{}", "🔍 Running database health checks...".blue().bold());
 
-    let pool = PgPool::connect(database_url).await?;
+    let pool = connect(database_url).await?;
 
     // Basic connectivity test
     println!("Testing database connectivity...");
     let version = sqlx::query!("SELECT version()")
         .fetch_one(&pool)
-        .await?;
+        .await
+        .db_step("read server version")?;
 
     println!("Database version: {}", version.version.unwrap_or_default().green());
 
@@ -275,7 +391,8 @@ async fn check_database(
             "SELECT COUNT(*) as count FROM information_schema.tables WHERE table_schema = 'public'"
         )
         .fetch_one(&pool)
-        .await?;
+        .await
+        .db_step("count tables")?;
 
         println!("  Public tables: {}", table_count.count.unwrap_or(0));
 
@@ -285,7 +402,8 @@ async fn check_database(
              WHERE schema_name NOT IN ('information_schema', 'pg_catalog', 'pg_toast', 'public')"
         )
         .fetch_one(&pool)
-        .await?;
+        .await
+        .db_step("count tenant schemas")?;
 
         println!("  Tenant schemas: {}", schema_count.count.unwrap_or(0));
     }
@@ -298,7 +416,8 @@ async fn check_database(
             "SELECT COUNT(*) as count FROM pg_stat_activity WHERE state = 'active'"
         )
         .fetch_one(&pool)
-        .await?;
+        .await
+        .db_step("count active queries")?;
 
         println!("  Active queries: {}", slow_queries.count.unwrap_or(0));
 
@@ -307,7 +426,8 @@ async fn check_database(
             "SELECT pg_size_pretty(pg_database_size(current_database())) as size"
         )
         .fetch_one(&pool)
-        .await?;
+        .await
+        .db_step("read database size")?;
 
         println!("  Database size: {}", db_size.size.unwrap_or_default().yellow());
     }
@@ -319,16 +439,23 @@ async fn check_database(
 
 async fn reset_database(
     database_url: &str,
-    tenant: Option<&str>,
+    tenant: Option<String>,
+    preserve: Vec<String>,
+    environment: &str,
+    approval_id: Option<&str>,
     force: bool,
 ) -> Result<()> {
     println!("{}", "⚠️ DANGER: Resetting database...".red().bold());
+    println!("Environment: {}", environment.yellow());
+    if !preserve.is_empty() {
+        println!("Preserving: {}", preserve.join(", ").cyan());
+    }
 
     if !force {
         use dialoguer::Confirm;
 
-        let prompt = match tenant {
-            Some(schema) => format!("This will DELETE ALL DATA in schema '{}'. Are you absolutely sure?", schema),
+        let prompt = match &tenant {
+            Some(tenant) => format!("This will DELETE ALL DATA of tenant '{}'. Are you absolutely sure?", tenant),
             None => "This will DELETE ALL DATA in the database. Are you absolutely sure?".to_string(),
         };
 
@@ -349,35 +476,133 @@ async fn reset_database(
         }
     }
 
-    let pool = PgPool::connect(database_url).await?;
+    let pool = connect(database_url).await?;
 
-    if let Some(schema) = tenant {
-        println!("Dropping schema: {}", schema.red());
+    let approval = if requires_approval(environment) {
+        let scope = reset_scope(tenant.as_deref(), &preserve);
+        Some(require_approval(&pool, "database reset", &scope, approval_id).await?)
+    } else {
+        None
+    };
 
-        let drop_sql = format!("DROP SCHEMA IF EXISTS {} CASCADE", schema);
-        sqlx::query(&drop_sql)
-            .execute(&pool)
-            .await?;
+    let options = ResetOptions {
+        tenant,
+        preserve,
+        migrator: &BUNDLED_MIGRATIONS,
+        tenant_template: TENANT_SCHEMA_TEMPLATE,
+        migration_source: migration_source(environment),
+    };
+    let summary = run_reset(&pool, &options, approval.as_ref()).await?;
+    pool.close().await;
 
-        println!("Schema '{}' has been dropped", schema);
-    } else {
-        return Err(anyhow!("Full database reset not implemented for safety. Use specific tenant reset."));
+    println!("\n{}", "📊 Reset Summary:".blue().bold());
+    println!("  Dropped schemas: {}", summary.dropped_schemas.join(", "));
+    if summary.preserved.is_empty() {
+        println!("  Preserved tables: none");
+    }
+    for table in &summary.preserved {
+        println!("  Preserved {}: {} row(s)", table.table.cyan(), table.rows);
+    }
+    if !summary.recreated_schemas.is_empty() {
+        println!("  Recreated tenant schemas: {}", summary.recreated_schemas.join(", "));
+    }
+    match summary.migration_version {
+        Some(version) => println!("  Migration version: {}", version.to_string().green()),
+        None => println!("  Migration version: none"),
     }
 
-    pool.close().await;
     println!("{}", "✅ Database reset completed".yellow().bold());
     Ok(())
 }
 
+/// Approve a pending request as a second operator
+async fn approve(database_url: &str, request_id: &str) -> Result<()> {
+    let pool = connect(database_url).await?;
+    let request = approve_request(&pool, request_id, &operator_identity()).await?;
+    pool.close().await;
+
+    println!("{}", "✅ Request approved".green().bold());
+    println!("Operation: {} ({})", request.operation, request.scope);
+    println!("Requested by: {}", request.requested_by.yellow());
+    println!("Valid until: {}", request.expires_at.format("%Y-%m-%d %H:%M:%S UTC"));
+    Ok(())
+}
+
+/// Gaps per number sequence; finance sequences drawn gap-free should show none
+async fn sequence_gaps(database_url: &str, tenant: Option<&str>, stale_hours: i64) -> Result<()> {
+    println!("{}", "🔢 Number sequence gaps".blue().bold());
+    let pool = connect(database_url).await?;
+
+    let tenant_id: Option<Uuid> = match tenant {
+        Some(tenant) => Some(
+            sqlx::query("SELECT id FROM public.tenants WHERE id::text = $1 OR schema_name = $1 OR name = $1")
+                .bind(tenant)
+                .fetch_optional(&pool)
+                .await
+                .db_step("look up tenant")?
+                .ok_or_else(|| DeployError::tenant_not_found(tenant))?
+                .get("id"),
+        ),
+        None => None,
+    };
+    let stale_before = Utc::now() - Duration::hours(stale_hours.max(0));
+    let report = numbering::gap_report(&pool, tenant_id, stale_before)
+        .await
+        .map_err(|e| DeployError::internal("read number sequences", e))?;
+    pool.close().await;
+
+    if report.is_empty() {
+        println!("No number sequences yet");
+        return Ok(());
+    }
+    let mut lapsed_total = 0;
+    for gaps in &report {
+        let policy = if gaps.no_gaps { "gap-free" } else { "gaps allowed" };
+        println!(
+            "\n{} {} ({}), highest number {}",
+            gaps.tenant_id.to_string().dimmed(),
+            gaps.sequence.cyan(),
+            policy,
+            gaps.high_water
+        );
+        println!("  Open blocks: {}", gaps.open.len());
+        if !gaps.returned.is_empty() {
+            println!("  Returned, awaiting reuse: {} number(s)", gaps.returned_count());
+        }
+        for block in &gaps.lapsed {
+            println!(
+                "  {} {}-{} leased by {} at {}",
+                "Lapsed:".yellow(),
+                block.first,
+                block.last,
+                block.instance_id.as_deref().unwrap_or("unknown instance"),
+                block.leased_at.format("%Y-%m-%d %H:%M:%S UTC")
+            );
+        }
+        lapsed_total += gaps.lapsed_count();
+    }
+
+    if lapsed_total > 0 {
+        println!(
+            "\n{}",
+            format!("⚠️  Up to {} number(s) lapsed in blocks of stopped instances", lapsed_total).yellow().bold()
+        );
+    } else {
+        println!("\n{}", "✅ No lapsed blocks".green());
+    }
+    Ok(())
+}
+
 async fn status_database(database_url: &str) -> Result<()> {
     println!("{}", "📊 Database Status".blue().bold());
 
-    let pool = PgPool::connect(database_url).await?;
+    let pool = connect(database_url).await?;
 
     // Basic status information
     let version = sqlx::query!("SELECT version()")
         .fetch_one(&pool)
-        .await?;
+        .await
+        .db_step("read server version")?;
 
     println!("Database version: {}", version.version.unwrap_or_default().green());
 
@@ -390,11 +615,46 @@ async fn status_database(database_url: &str) -> Result<()> {
          WHERE schema_name NOT IN ('information_schema', 'pg_catalog', 'pg_toast')"
     )
     .fetch_one(&pool)
-    .await?;
+    .await
+    .db_step("count schemas")?;
 
     println!("Total schemas: {}", schema_count.count.unwrap_or(0));
 
     pool.close().await;
     println!("{}", "✅ Status check completed".green());
     Ok(())
+}
+
+/// Audited migration runs, newest first
+async fn migration_history(database_url: &str, limit: i64, slow_threshold: std::time::Duration) -> Result<()> {
+    let pool = connect(database_url).await?;
+    let records = migration_audit::history(&pool, limit)
+        .await
+        .map_err(|e| DeployError::internal("read migration audit", e))?;
+    pool.close().await;
+
+    println!("\n{}", "📜 Migration History:".blue().bold());
+    if records.is_empty() {
+        println!("  No audited migration runs");
+    }
+    for record in &records {
+        let outcome = if record.success { "ok".green() } else { "FAILED".red().bold() };
+        println!(
+            "  {:>4} {} [{}] {} ms, {} by {} in {}",
+            record.version,
+            record.description,
+            outcome,
+            record.duration_ms,
+            record.started_at.format("%Y-%m-%d %H:%M:%S UTC"),
+            record.applied_by,
+            record.environment
+        );
+        if record.is_slow(slow_threshold) {
+            println!("       {}", format!("⚠️  slow: ran longer than {}s", slow_threshold.as_secs()).yellow());
+        }
+        if let Some(error) = &record.error {
+            println!("       {}", error.red());
+        }
+    }
+    Ok(())
 }
\ No newline at end of file
@@ -1,20 +1,30 @@
 //! Docker management command implementations
 
-use anyhow::{anyhow, Result};
 use colored::*;
 use serde_json::Value;
+use std::time::{Duration, Instant};
 use tokio::process::Command;
 
-use crate::DockerCommands;
+use super::env_file::{self, EnvTarget};
+use crate::error::{DeployError, ProcessResultExt, Result};
+use crate::{config::Config, DockerCommands};
 
-pub async fn execute_docker_command(cmd: DockerCommands) -> Result<()> {
+/// Pause between two looks at the health of starting services
+const HEALTH_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+pub async fn execute_docker_command(cmd: DockerCommands, config: &Config) -> Result<()> {
     match cmd {
-        DockerCommands::Start { service, services, detach } => {
+        DockerCommands::Start { service, services, detach, wait_for_healthy, timeout } => {
             let mut all_services = services;
             if let Some(s) = service {
                 all_services.push(s);
             }
-            start_services(all_services, detach).await
+            // Waiting needs docker-compose to return, so it always detaches
+            let started = start_services(all_services, detach || wait_for_healthy).await?;
+            if wait_for_healthy {
+                wait_until_healthy(&started, Duration::from_secs(timeout)).await?;
+            }
+            Ok(())
         }
         DockerCommands::Stop { service, services, force } => {
             let mut all_services = services;
@@ -33,12 +43,13 @@ pub async fn execute_docker_command(cmd: DockerCommands) -> Result<()> {
             show_logs(&service, follow).await
         }
         DockerCommands::Update { force } => {
-            update_services(force).await
+            update_services(force, config).await
         }
     }
 }
 
-async fn start_services(services: Vec<String>, detach: bool) -> Result<()> {
+/// Start the services, all of them if none are named; returns the started ones
+async fn start_services(services: Vec<String>, detach: bool) -> Result<Vec<String>> {
     println!("{}", "🚀 Starting ERP system services...".blue().bold());
 
     // Check if Docker is running
@@ -72,12 +83,7 @@ async fn start_services(services: Vec<String>, detach: bool) -> Result<()> {
         }
     );
 
-    let output = cmd.output().await?;
-
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(anyhow!("Failed to start services: {}", stderr));
-    }
+    let output = cmd.output().await.checked("docker-compose", "start services")?;
 
     let stdout = String::from_utf8_lossy(&output.stdout);
     if !stdout.is_empty() {
@@ -85,7 +91,106 @@ async fn start_services(services: Vec<String>, detach: bool) -> Result<()> {
     }
 
     println!("{}", "✅ Services started successfully".green().bold());
-    Ok(())
+    Ok(services_to_start)
+}
+
+/// Whether a container is ready, from the `State` of `docker inspect`
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Readiness {
+    Ready,
+    /// The state it is in, e.g. `starting`, `unhealthy` or `exited`
+    NotReady(String),
+}
+
+/// A container must be running and, if it defines a healthcheck, healthy
+fn container_readiness(state: &Value) -> Readiness {
+    let status = state["Status"].as_str().unwrap_or("unknown");
+    if status != "running" {
+        return Readiness::NotReady(status.to_string());
+    }
+    match state["Health"]["Status"].as_str() {
+        None | Some("healthy") => Readiness::Ready,
+        Some(health) => Readiness::NotReady(health.to_string()),
+    }
+}
+
+/// A service is ready when it has containers and all of them are
+fn service_readiness(states: &[Value]) -> Readiness {
+    if states.is_empty() {
+        return Readiness::NotReady("no container".to_string());
+    }
+    states
+        .iter()
+        .map(container_readiness)
+        .find(|readiness| *readiness != Readiness::Ready)
+        .unwrap_or(Readiness::Ready)
+}
+
+/// The `docker inspect` state of each container of the service
+async fn container_states(service: &str) -> Result<Vec<Value>> {
+    let output = Command::new("docker-compose")
+        .arg("ps")
+        .arg("-q")
+        .arg(service)
+        .output()
+        .await
+        .checked("docker-compose", "list service containers")?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let ids: Vec<&str> = stdout.split_whitespace().collect();
+    if ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    // A container removed since it was listed counts as not there yet
+    let output = Command::new("docker")
+        .arg("inspect")
+        .arg("--format")
+        .arg("{{json .State}}")
+        .args(&ids)
+        .output()
+        .await
+        .map_err(|e| DeployError::from_spawn(e, "docker", "inspect service containers"))?;
+    if !output.status.success() {
+        return Ok(Vec::new());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+/// Poll the services until all are ready; fails naming the others once `timeout` passed
+async fn wait_until_healthy(services: &[String], timeout: Duration) -> Result<()> {
+    println!("{}", format!("⏳ Waiting up to {}s for services to become healthy...", timeout.as_secs()).blue());
+
+    let deadline = Instant::now() + timeout;
+    let mut pending = services.to_vec();
+    loop {
+        let mut not_ready = Vec::new();
+        for service in pending {
+            match service_readiness(&container_states(&service).await?) {
+                Readiness::Ready => println!("✅ {} is healthy", service.green()),
+                Readiness::NotReady(state) => not_ready.push((service, state)),
+            }
+        }
+        if not_ready.is_empty() {
+            println!("{}", "✅ All services are healthy".green().bold());
+            return Ok(());
+        }
+
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            for (service, state) in &not_ready {
+                println!("{}", format!("❌ {} is still {}", service, state).red());
+            }
+            return Err(DeployError::ServicesUnhealthy {
+                services: not_ready.iter().map(|(service, state)| format!("{} ({})", service, state)).collect(),
+                timeout_secs: timeout.as_secs(),
+            });
+        }
+        tokio::time::sleep(HEALTH_POLL_INTERVAL.min(left)).await;
+        pending = not_ready.into_iter().map(|(service, _)| service).collect();
+    }
 }
 
 async fn stop_services(services: Vec<String>, force: bool) -> Result<()> {
@@ -112,12 +217,7 @@ async fn stop_services(services: Vec<String>, force: bool) -> Result<()> {
         cmd.arg(service);
     }
 
-    let output = cmd.output().await?;
-
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(anyhow!("Failed to stop services: {}", stderr));
-    }
+    cmd.output().await.checked("docker-compose", "stop services")?;
 
     println!("{}", "✅ Services stopped successfully".green().bold());
     Ok(())
@@ -143,12 +243,7 @@ async fn restart_services(services: Vec<String>) -> Result<()> {
         cmd.arg(service);
     }
 
-    let output = cmd.output().await?;
-
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(anyhow!("Failed to restart services: {}", stderr));
-    }
+    cmd.output().await.checked("docker-compose", "restart services")?;
 
     println!("{}", "✅ Services restarted successfully".green().bold());
     Ok(())
@@ -164,12 +259,8 @@ async fn show_status(format: &str) -> Result<()> {
         .arg("--format")
         .arg("json")
         .output()
-        .await?;
-
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(anyhow!("Failed to get container status: {}", stderr));
-    }
+        .await
+        .checked("docker-compose", "read container status")?;
 
     let stdout = String::from_utf8_lossy(&output.stdout);
 
@@ -257,25 +348,22 @@ async fn show_logs(service: &str, follow: bool) -> Result<()> {
 
     let output = if follow {
         // For follow mode, stream the output
-        let mut child = cmd.spawn()?;
+        let mut child = cmd
+            .spawn()
+            .map_err(|e| DeployError::from_spawn(e, "docker-compose", "follow service logs"))?;
         child.wait().await?;
         return Ok(());
     } else {
-        cmd.output().await?
+        cmd.output().await.checked("docker-compose", "read service logs")?
     };
 
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(anyhow!("Failed to get logs: {}", stderr));
-    }
-
     let stdout = String::from_utf8_lossy(&output.stdout);
     println!("{}", stdout);
 
     Ok(())
 }
 
-async fn update_services(force: bool) -> Result<()> {
+async fn update_services(force: bool, config: &Config) -> Result<()> {
     println!("{}", "📦 Updating container images...".blue().bold());
 
     check_docker_running().await?;
@@ -301,7 +389,10 @@ async fn update_services(force: bool) -> Result<()> {
         let mut cmd = Command::new("docker-compose");
         cmd.arg("pull").arg(service);
 
-        let output = cmd.output().await?;
+        let output = cmd
+            .output()
+            .await
+            .map_err(|e| DeployError::from_spawn(e, "docker-compose", "pull images"))?;
         if !output.status.success() {
             let stderr = String::from_utf8_lossy(&output.stderr);
             println!("{}", format!("Warning: Failed to pull {}: {}", service, stderr).yellow());
@@ -310,6 +401,11 @@ async fn update_services(force: bool) -> Result<()> {
         }
     }
 
+    // Recreated containers pick up the .env file, so bring it in line with the configuration first
+    let target = EnvTarget::from_config(config, None, None);
+    let generated = env_file::generate(&target)?;
+    env_file::print_generated(&target, &generated);
+
     // Restart services with new images
     println!("Restarting services with updated images...");
     let mut cmd = Command::new("docker-compose");
@@ -321,11 +417,7 @@ async fn update_services(force: bool) -> Result<()> {
         cmd.arg(service);
     }
 
-    let output = cmd.output().await?;
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(anyhow!("Failed to restart services: {}", stderr));
-    }
+    cmd.output().await.checked("docker-compose", "recreate services")?;
 
     println!("{}", "✅ Services updated successfully".green().bold());
     Ok(())
@@ -339,7 +431,48 @@ async fn check_docker_running() -> Result<()> {
 
     match output {
         Ok(result) if result.status.success() => Ok(()),
-        Ok(_) => Err(anyhow!("Docker is not running or not accessible")),
-        Err(_) => Err(anyhow!("Docker command not found. Please install Docker.")),
+        Ok(result) => Err(DeployError::DockerUnavailable {
+            step: "check Docker daemon".to_string(),
+            detail: format!(
+                "`docker info` failed: {}",
+                String::from_utf8_lossy(&result.stderr).trim()
+            ),
+        }),
+        Err(e) => Err(DeployError::from_spawn(e, "docker", "check Docker daemon")),
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_container_with_healthcheck_must_be_healthy() {
+        let starting = json!({ "Status": "running", "Health": { "Status": "starting" } });
+        let healthy = json!({ "Status": "running", "Health": { "Status": "healthy" } });
+        assert_eq!(container_readiness(&starting), Readiness::NotReady("starting".to_string()));
+        assert_eq!(container_readiness(&healthy), Readiness::Ready);
+
+        // A stopped container is not ready whatever its last health was
+        let exited = json!({ "Status": "exited", "Health": { "Status": "healthy" } });
+        assert_eq!(container_readiness(&exited), Readiness::NotReady("exited".to_string()));
+    }
+
+    #[test]
+    fn test_container_without_healthcheck_only_needs_to_run() {
+        assert_eq!(container_readiness(&json!({ "Status": "running" })), Readiness::Ready);
+        assert_eq!(
+            container_readiness(&json!({ "Status": "restarting" })),
+            Readiness::NotReady("restarting".to_string())
+        );
+    }
+
+    #[test]
+    fn test_service_is_ready_when_all_its_containers_are() {
+        let running = json!({ "Status": "running" });
+        let unhealthy = json!({ "Status": "running", "Health": { "Status": "unhealthy" } });
+        assert_eq!(service_readiness(&[]), Readiness::NotReady("no container".to_string()));
+        assert_eq!(service_readiness(&[running.clone(), running.clone()]), Readiness::Ready);
+        assert_eq!(service_readiness(&[running, unhealthy]), Readiness::NotReady("unhealthy".to_string()));
+    }
+}
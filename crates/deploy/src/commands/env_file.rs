@@ -0,0 +1,782 @@
+//! `.env` file management (`erp-deploy env`)
+//!
+//! docker compose and the API read their settings from a `.env` file that used
+//! to be edited by hand and drifted from the TOML configuration. `env generate`
+//! renders it from the application config: API settings under the names
+//! `Config::load` reads them from (the dotted key upper-cased, `.` becoming
+//! `_`), plus the variables the compose file needs, derived from the database
+//! and Redis URLs.
+//!
+//! Lines below [`MANUAL_MARKER`] are kept verbatim. A file without the marker
+//! has the keys the configuration does not produce moved below it on the first
+//! run, so nothing added by hand is lost.
+//!
+//! `env diff` prints key-level drift with secrets masked; `env check` fails with
+//! [`DeployError::EnvDrift`] on drift and on variables the compose file uses but
+//! the file does not set.
+
+use colored::*;
+use regex::Regex;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::error::{DeployError, IoResultExt, Result};
+use crate::{config::Config, EnvCommands};
+
+/// Separates generated variables from entries maintained by hand
+pub const MANUAL_MARKER: &str = "# ---- manual entries below are kept by `erp-deploy env generate` ----";
+
+/// Application config keys exported for the API, when set
+const API_KEYS: &[&str] = &[
+    "server.host",
+    "server.port",
+    "server.workers",
+    "database.url",
+    "database.max_connections",
+    "database.min_connections",
+    "redis.url",
+    "redis.max_connections",
+    "jwt.secret",
+    "jwt.access_token_expiry",
+    "jwt.refresh_token_expiry",
+    "security.aes_encryption_key",
+    "email.provider",
+    "email.smtp_host",
+    "email.smtp_port",
+    "email.smtp_username",
+    "email.smtp_password",
+    "metrics.enabled",
+    "metrics.port",
+];
+
+/// Parts of a variable name that mark its value as secret
+const SECRET_MARKERS: &[&str] = &["PASSWORD", "SECRET", "TOKEN", "_KEY"];
+
+const MASK: &str = "********";
+
+pub async fn execute_env_command(cmd: EnvCommands, config: &Config) -> Result<()> {
+    match cmd {
+        EnvCommands::Generate { environment, file, stdout } => {
+            let target = EnvTarget::from_config(config, environment.as_deref(), file.as_deref());
+            if stdout {
+                let (variables, current) = plan(&target)?;
+                print!("{}", render(&variables, &current.manual_section(&variables), &target.app_config));
+                return Ok(());
+            }
+            let generated = generate(&target)?;
+            print_generated(&target, &generated);
+            Ok(())
+        }
+        EnvCommands::Diff { environment, file } => {
+            let target = EnvTarget::from_config(config, environment.as_deref(), file.as_deref());
+            let report = inspect(&target)?;
+            print_report(&target, &report);
+            Ok(())
+        }
+        EnvCommands::Check { environment, file } => {
+            let target = EnvTarget::from_config(config, environment.as_deref(), file.as_deref());
+            let report = inspect(&target)?;
+            print_report(&target, &report);
+            report.into_result(&target.env_file)
+        }
+    }
+}
+
+/// Where the configuration comes from and where the `.env` file goes
+#[derive(Debug, Clone)]
+pub struct EnvTarget {
+    pub env_file: PathBuf,
+    pub compose_file: PathBuf,
+    /// Later files override earlier ones; missing files are skipped
+    pub app_config: Vec<PathBuf>,
+    pub environment: String,
+}
+
+impl EnvTarget {
+    pub fn from_config(config: &Config, environment: Option<&str>, file: Option<&str>) -> Self {
+        let environment = environment.unwrap_or(&config.default_environment).to_string();
+        let compose_file = PathBuf::from(&config.docker.compose_file);
+        let base = compose_file.parent().map(Path::to_path_buf).unwrap_or_default();
+        let env_file = file
+            .or(config.env.file.as_deref())
+            .map(PathBuf::from)
+            .unwrap_or_else(|| base.join(".env"));
+        let app_config = if config.env.app_config.is_empty() {
+            default_app_config(&base, &environment)
+        } else {
+            config.env.app_config.iter().map(PathBuf::from).collect()
+        };
+
+        Self {
+            env_file,
+            compose_file,
+            app_config,
+            environment,
+        }
+    }
+
+    /// The layout `scripts/install.sh` leaves behind: the release in
+    /// `install_dir` and the generated secrets in `/etc/erp-system/config.toml`
+    pub fn for_install(install_dir: &Path, environment: &str) -> Self {
+        let mut app_config = default_app_config(install_dir, environment);
+        app_config.push(PathBuf::from("/etc/erp-system/config.toml"));
+
+        Self {
+            env_file: install_dir.join(".env"),
+            compose_file: install_dir.join("docker-compose.yml"),
+            app_config,
+            environment: environment.to_string(),
+        }
+    }
+}
+
+fn default_app_config(base: &Path, environment: &str) -> Vec<PathBuf> {
+    vec![
+        base.join("config").join("default.toml"),
+        base.join("config").join(format!("{}.toml", environment)),
+    ]
+}
+
+/// Merge the application config files that exist, later files winning key by key
+pub fn load_app_config(paths: &[PathBuf]) -> Result<toml::Value> {
+    let mut merged = toml::Value::Table(toml::map::Map::new());
+    let mut found = false;
+    for path in paths.iter().filter(|p| p.exists()) {
+        let content = std::fs::read_to_string(path).io_step(path, "read application config")?;
+        let document: toml::Value = toml::from_str(&content).map_err(|e| DeployError::Config {
+            path: Some(path.clone()),
+            message: format!("invalid application config in {}", path.display()),
+            source: Some(Box::new(e)),
+        })?;
+        merge(&mut merged, document);
+        found = true;
+    }
+
+    if !found {
+        let searched: Vec<String> = paths.iter().map(|p| p.display().to_string()).collect();
+        return Err(DeployError::config(
+            None,
+            format!("No application config found (looked for {}); set env.app_config", searched.join(", ")),
+        ));
+    }
+    Ok(merged)
+}
+
+fn merge(base: &mut toml::Value, overlay: toml::Value) {
+    match (base, overlay) {
+        (toml::Value::Table(base), toml::Value::Table(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// Environment variable `Config::load` reads a dotted config key from
+pub fn env_name(key: &str) -> String {
+    key.replace('.', "_").to_ascii_uppercase()
+}
+
+/// The variables `env generate` writes, in file order
+pub fn render_variables(app: &toml::Value, environment: &str) -> Result<Vec<(String, String)>> {
+    let mut variables = vec![("ENVIRONMENT".to_string(), environment.to_string())];
+    for key in API_KEYS {
+        if let Some(value) = lookup(app, key) {
+            variables.push((env_name(key), scalar(key, value)?));
+        }
+    }
+
+    // What the postgres and redis services are started with must match what the API connects with
+    if let Some(url) = lookup(app, "database.url").and_then(toml::Value::as_str) {
+        let url = parse_url("database.url", url)?;
+        variables.push(("POSTGRES_USER".to_string(), percent_decode(url.username())));
+        variables.push(("POSTGRES_PASSWORD".to_string(), percent_decode(url.password().unwrap_or_default())));
+        variables.push(("POSTGRES_DB".to_string(), url.path().trim_start_matches('/').to_string()));
+    }
+    if let Some(url) = lookup(app, "redis.url").and_then(toml::Value::as_str) {
+        let url = parse_url("redis.url", url)?;
+        variables.push(("REDIS_PASSWORD".to_string(), percent_decode(url.password().unwrap_or_default())));
+    }
+
+    Ok(variables)
+}
+
+fn lookup<'a>(document: &'a toml::Value, key: &str) -> Option<&'a toml::Value> {
+    key.split('.').try_fold(document, |node, segment| node.get(segment))
+}
+
+fn scalar(key: &str, value: &toml::Value) -> Result<String> {
+    match value {
+        toml::Value::String(s) => Ok(s.clone()),
+        toml::Value::Integer(i) => Ok(i.to_string()),
+        toml::Value::Float(f) => Ok(f.to_string()),
+        toml::Value::Boolean(b) => Ok(b.to_string()),
+        other => Err(DeployError::config(
+            None,
+            format!("{} is a {} and cannot be exported as an environment variable", key, other.type_str()),
+        )),
+    }
+}
+
+fn parse_url(key: &str, value: &str) -> Result<url::Url> {
+    url::Url::parse(value).map_err(|e| DeployError::config(None, format!("{} is not a valid URL: {}", key, e)))
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3).and_then(|h| std::str::from_utf8(h).ok());
+        match (bytes[i], hex.and_then(|h| u8::from_str_radix(h, 16).ok())) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// One `KEY=value` line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnvEntry {
+    pub key: String,
+    pub value: String,
+    /// The line as written
+    pub line: String,
+}
+
+/// An existing `.env` file, split at the manual marker
+#[derive(Debug, Clone, Default)]
+pub struct EnvFile {
+    /// Variables above the marker
+    pub entries: Vec<EnvEntry>,
+    /// Lines below the marker, verbatim
+    pub manual: Vec<String>,
+    pub has_marker: bool,
+}
+
+impl EnvFile {
+    pub fn parse(content: &str) -> Self {
+        let mut file = Self::default();
+        for line in content.lines() {
+            if file.has_marker {
+                file.manual.push(line.to_string());
+            } else if line.trim() == MANUAL_MARKER {
+                file.has_marker = true;
+            } else if let Some(entry) = parse_line(line) {
+                file.entries.push(entry);
+            }
+        }
+        file
+    }
+
+    fn manual_entries(&self) -> impl Iterator<Item = EnvEntry> + '_ {
+        self.manual.iter().filter_map(|line| parse_line(line))
+    }
+
+    /// Values as docker compose sees them, a later line winning
+    pub fn values(&self) -> BTreeMap<String, String> {
+        self.entries
+            .iter()
+            .cloned()
+            .chain(self.manual_entries())
+            .map(|e| (e.key, e.value))
+            .collect()
+    }
+
+    /// Lines to keep below the marker when the file is regenerated
+    pub fn manual_section(&self, generated: &[(String, String)]) -> Vec<String> {
+        if self.has_marker {
+            return self.manual.clone();
+        }
+        let generated: BTreeSet<&str> = generated.iter().map(|(k, _)| k.as_str()).collect();
+        self.entries
+            .iter()
+            .filter(|e| !generated.contains(e.key.as_str()))
+            .map(|e| e.line.clone())
+            .collect()
+    }
+}
+
+fn parse_line(line: &str) -> Option<EnvEntry> {
+    let trimmed = line.trim();
+    if trimmed.is_empty() || trimmed.starts_with('#') {
+        return None;
+    }
+    let (key, value) = trimmed.strip_prefix("export ").unwrap_or(trimmed).split_once('=')?;
+    let key = key.trim();
+    if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return None;
+    }
+
+    Some(EnvEntry {
+        key: key.to_string(),
+        value: unquote(value.trim()),
+        line: line.to_string(),
+    })
+}
+
+fn unquote(value: &str) -> String {
+    if value.len() >= 2 && value.starts_with('\'') && value.ends_with('\'') {
+        return value[1..value.len() - 1].to_string();
+    }
+    if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+        return value[1..value.len() - 1].replace("\\\"", "\"").replace("\\\\", "\\");
+    }
+    // Unquoted values end at an inline comment
+    match value.find(" #") {
+        Some(i) => value[..i].trim_end().to_string(),
+        None => value.to_string(),
+    }
+}
+
+fn quote(value: &str) -> String {
+    let plain = value
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || "-_./:@,+=%".contains(c));
+    if plain {
+        value.to_string()
+    } else if !value.contains('\'') {
+        format!("'{}'", value)
+    } else {
+        format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+    }
+}
+
+/// The file contents for `variables`, with `manual` kept below the marker
+pub fn render(variables: &[(String, String)], manual: &[String], sources: &[PathBuf]) -> String {
+    let sources: Vec<String> = sources.iter().map(|p| p.display().to_string()).collect();
+    let mut out = format!(
+        "# Generated by `erp-deploy env generate` from {}\n\
+         # Edits above the manual marker are overwritten; add extra keys below it.\n",
+        sources.join(", ")
+    );
+    for (key, value) in variables {
+        out.push_str(&format!("{}={}\n", key, quote(value)));
+    }
+    out.push('\n');
+    out.push_str(MANUAL_MARKER);
+    out.push('\n');
+    for line in manual {
+        out.push_str(line);
+        out.push('\n');
+    }
+    out
+}
+
+/// Difference between the file and what `env generate` would write
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EnvDrift {
+    /// The file sets a different value than the configuration
+    Changed { key: String, current: String, expected: String },
+    /// The configuration produces it but the file lacks it
+    Missing { key: String, expected: String },
+    /// Above the marker without coming from the configuration
+    Unmanaged { key: String, current: String },
+}
+
+impl EnvDrift {
+    /// One line for the operator, secrets masked
+    pub fn describe(&self) -> String {
+        match self {
+            Self::Changed { key, current, expected } => {
+                let (from, to) = (mask(key, current), mask(key, expected));
+                if from == to {
+                    format!("~ {}: {} -> {} (secret differs)", key, from, to)
+                } else {
+                    format!("~ {}: {} -> {}", key, from, to)
+                }
+            }
+            Self::Missing { key, expected } => format!("+ {}: {} (missing from the file)", key, mask(key, expected)),
+            Self::Unmanaged { key, current } => {
+                format!("- {}: {} (not from the configuration; move it below the manual marker)", key, mask(key, current))
+            }
+        }
+    }
+}
+
+pub fn is_secret(key: &str) -> bool {
+    let key = key.to_ascii_uppercase();
+    SECRET_MARKERS.iter().any(|marker| key.contains(marker))
+}
+
+/// A value safe to print: secrets hidden entirely, URL passwords replaced
+pub fn mask(key: &str, value: &str) -> String {
+    if value.is_empty() {
+        return "<empty>".to_string();
+    }
+    if is_secret(key) {
+        return MASK.to_string();
+    }
+    match url::Url::parse(value) {
+        Ok(mut url) if url.password().is_some() => {
+            let _ = url.set_password(Some(MASK));
+            url.to_string()
+        }
+        _ => value.to_string(),
+    }
+}
+
+/// Key-level drift of `current` from the generated `variables`
+pub fn diff(current: &EnvFile, variables: &[(String, String)]) -> Vec<EnvDrift> {
+    let values = current.values();
+    let mut drift: Vec<EnvDrift> = variables
+        .iter()
+        .filter_map(|(key, expected)| match values.get(key) {
+            None => Some(EnvDrift::Missing {
+                key: key.clone(),
+                expected: expected.clone(),
+            }),
+            Some(value) if value != expected => Some(EnvDrift::Changed {
+                key: key.clone(),
+                current: value.clone(),
+                expected: expected.clone(),
+            }),
+            Some(_) => None,
+        })
+        .collect();
+
+    let generated: BTreeSet<&str> = variables.iter().map(|(k, _)| k.as_str()).collect();
+    let mut seen = BTreeSet::new();
+    for entry in &current.entries {
+        if !generated.contains(entry.key.as_str()) && seen.insert(entry.key.as_str()) {
+            drift.push(EnvDrift::Unmanaged {
+                key: entry.key.clone(),
+                current: entry.value.clone(),
+            });
+        }
+    }
+    drift
+}
+
+/// Variables the compose file interpolates; `$$` escapes and comment lines are ignored
+pub fn compose_variables(compose: &str) -> BTreeSet<String> {
+    let pattern = Regex::new(r"\$\{([A-Za-z_][A-Za-z0-9_]*)[^}]*\}|\$([A-Za-z_][A-Za-z0-9_]*)")
+        .expect("compose variable pattern is valid");
+    compose
+        .lines()
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .flat_map(|line| {
+            let line = line.replace("$$", "");
+            pattern
+                .captures_iter(&line)
+                .filter_map(|c| c.get(1).or_else(|| c.get(2)).map(|m| m.as_str().to_string()))
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// State of the `.env` file against the configuration and the compose file
+#[derive(Debug, Clone, Default)]
+pub struct EnvReport {
+    pub drift: Vec<EnvDrift>,
+    /// Used by the compose file but set neither by generation nor by hand.
+    /// Variables with a `${VAR:-default}` count too: the fallback is how a
+    /// stale password survives unnoticed.
+    pub missing_compose: Vec<String>,
+}
+
+impl EnvReport {
+    pub fn build(current: &EnvFile, variables: &[(String, String)], compose: Option<&str>) -> Self {
+        let mut available: BTreeSet<String> = variables.iter().map(|(k, _)| k.clone()).collect();
+        available.extend(current.manual_entries().map(|e| e.key));
+        let missing_compose = compose
+            .map(compose_variables)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|name| !available.contains(name))
+            .collect();
+
+        Self {
+            drift: diff(current, variables),
+            missing_compose,
+        }
+    }
+
+    pub fn is_clean(&self) -> bool {
+        self.drift.is_empty() && self.missing_compose.is_empty()
+    }
+
+    fn into_result(self, env_file: &Path) -> Result<()> {
+        if self.is_clean() {
+            return Ok(());
+        }
+        Err(DeployError::EnvDrift {
+            path: env_file.to_path_buf(),
+            drifted: self.drift.len(),
+            missing: self.missing_compose.len(),
+        })
+    }
+}
+
+/// Result of `env generate`
+#[derive(Debug, Clone)]
+pub struct Generated {
+    pub variables: Vec<(String, String)>,
+    /// What the rewrite changed
+    pub drift: Vec<EnvDrift>,
+    pub manual_lines: usize,
+}
+
+impl Generated {
+    pub fn value(&self, key: &str) -> Option<&str> {
+        self.variables.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+    }
+}
+
+fn read_env_file(path: &Path) -> Result<EnvFile> {
+    if !path.exists() {
+        return Ok(EnvFile::default());
+    }
+    let content = std::fs::read_to_string(path).io_step(path, "read .env file")?;
+    Ok(EnvFile::parse(&content))
+}
+
+fn plan(target: &EnvTarget) -> Result<(Vec<(String, String)>, EnvFile)> {
+    let app = load_app_config(&target.app_config)?;
+    let variables = render_variables(&app, &target.environment)?;
+    Ok((variables, read_env_file(&target.env_file)?))
+}
+
+/// Compare the `.env` file with the configuration and the compose file without writing anything
+pub fn inspect(target: &EnvTarget) -> Result<EnvReport> {
+    let (variables, current) = plan(target)?;
+    let compose = if target.compose_file.exists() {
+        Some(std::fs::read_to_string(&target.compose_file).io_step(&target.compose_file, "read compose file")?)
+    } else {
+        None
+    };
+    Ok(EnvReport::build(&current, &variables, compose.as_deref()))
+}
+
+/// Rewrite the `.env` file from the configuration, keeping the manual section
+pub fn generate(target: &EnvTarget) -> Result<Generated> {
+    let (variables, current) = plan(target)?;
+    let manual = current.manual_section(&variables);
+    let content = render(&variables, &manual, &target.app_config);
+    write_env_file(&target.env_file, &content)?;
+
+    Ok(Generated {
+        drift: diff(&current, &variables),
+        manual_lines: manual.iter().filter(|l| parse_line(l).is_some()).count(),
+        variables,
+    })
+}
+
+/// Replace the file atomically; it holds secrets, so only the owner may read it
+fn write_env_file(path: &Path, content: &str) -> Result<()> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let mut file = tempfile::NamedTempFile::new_in(dir).io_step(dir, "create .env file")?;
+    file.write_all(content.as_bytes()).io_step(path, "write .env file")?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.as_file()
+            .set_permissions(std::fs::Permissions::from_mode(0o600))
+            .io_step(path, "restrict .env file permissions")?;
+    }
+    file.persist(path).map_err(|e| e.error).io_step(path, "write .env file")?;
+    Ok(())
+}
+
+pub fn print_generated(target: &EnvTarget, generated: &Generated) {
+    println!(
+        "{} {} ({} variables, {} manual entries kept)",
+        "📝 Wrote".blue().bold(),
+        target.env_file.display(),
+        generated.variables.len(),
+        generated.manual_lines
+    );
+    for drift in &generated.drift {
+        println!("  {}", drift.describe().yellow());
+    }
+}
+
+fn print_report(target: &EnvTarget, report: &EnvReport) {
+    if report.is_clean() {
+        println!("{} {} matches the configuration", "✅".green(), target.env_file.display());
+        return;
+    }
+    if !report.drift.is_empty() {
+        println!("{} {}:", "⚠️ Drift in".yellow().bold(), target.env_file.display());
+        for drift in &report.drift {
+            let line = drift.describe();
+            match drift {
+                EnvDrift::Changed { .. } => println!("  {}", line.yellow()),
+                EnvDrift::Missing { .. } => println!("  {}", line.green()),
+                EnvDrift::Unmanaged { .. } => println!("  {}", line.red()),
+            }
+        }
+    }
+    if !report.missing_compose.is_empty() {
+        println!(
+            "{} {} uses variables the .env file does not set:",
+            "⚠️".yellow(),
+            target.compose_file.display()
+        );
+        for name in &report.missing_compose {
+            println!("  {}", name.red());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const APP: &str = r#"
+        [server]
+        host = "0.0.0.0"
+        port = 8080
+
+        [database]
+        url = "postgresql://erp_admin:db%2Fpass@db:5432/erp_main"
+
+        [redis]
+        url = "redis://:redispass@redis:6379"
+
+        [jwt]
+        secret = "fresh-jwt-secret-of-at-least-32-chars"
+    "#;
+
+    fn variables() -> Vec<(String, String)> {
+        render_variables(&toml::from_str(APP).unwrap(), "production").unwrap()
+    }
+
+    fn value<'a>(variables: &'a [(String, String)], key: &str) -> Option<&'a str> {
+        variables.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+    }
+
+    #[test]
+    fn test_variables_follow_config_and_compose_names() {
+        let variables = variables();
+        assert_eq!(value(&variables, "ENVIRONMENT"), Some("production"));
+        assert_eq!(value(&variables, "SERVER_PORT"), Some("8080"));
+        assert_eq!(value(&variables, "JWT_SECRET"), Some("fresh-jwt-secret-of-at-least-32-chars"));
+        assert_eq!(value(&variables, "POSTGRES_USER"), Some("erp_admin"));
+        assert_eq!(value(&variables, "POSTGRES_PASSWORD"), Some("db/pass"));
+        assert_eq!(value(&variables, "POSTGRES_DB"), Some("erp_main"));
+        assert_eq!(value(&variables, "REDIS_PASSWORD"), Some("redispass"));
+        assert_eq!(value(&variables, "SERVER_WORKERS"), None);
+    }
+
+    #[test]
+    fn test_diff_detects_changed_missing_and_unmanaged_keys() {
+        let variables = variables();
+        let mut current = render(&variables, &[], &[]);
+        current = current
+            .replace("JWT_SECRET=fresh-jwt-secret-of-at-least-32-chars", "JWT_SECRET=stale-secret")
+            .replace("SERVER_PORT=8080\n", "")
+            .replace("ENVIRONMENT=production", "ENVIRONMENT=production\nLEGACY_FLAG=1");
+
+        let drift = diff(&EnvFile::parse(&current), &variables);
+        assert_eq!(drift.len(), 3);
+        assert!(drift.contains(&EnvDrift::Changed {
+            key: "JWT_SECRET".into(),
+            current: "stale-secret".into(),
+            expected: "fresh-jwt-secret-of-at-least-32-chars".into(),
+        }));
+        assert!(drift.contains(&EnvDrift::Missing {
+            key: "SERVER_PORT".into(),
+            expected: "8080".into(),
+        }));
+        assert!(drift.contains(&EnvDrift::Unmanaged {
+            key: "LEGACY_FLAG".into(),
+            current: "1".into(),
+        }));
+
+        // A regenerated file has no drift
+        assert!(diff(&EnvFile::parse(&render(&variables, &[], &[])), &variables).is_empty());
+    }
+
+    #[test]
+    fn test_drift_report_masks_secrets() {
+        let current = EnvFile::parse(
+            "JWT_SECRET=stale-secret\n\
+             POSTGRES_PASSWORD=old-db-pass\n\
+             DATABASE_URL=postgresql://erp_admin:old-db-pass@db:5432/erp_main\n",
+        );
+        let lines: Vec<String> = diff(&current, &variables()).iter().map(EnvDrift::describe).collect();
+        let report = lines.join("\n");
+
+        for secret in ["stale-secret", "fresh-jwt-secret", "old-db-pass", "db/pass", "db%2Fpass", "redispass"] {
+            assert!(!report.contains(secret), "{} leaked into:\n{}", secret, report);
+        }
+        assert!(report.contains("~ JWT_SECRET: ******** -> ******** (secret differs)"));
+        // Non-secret parts of a URL stay visible
+        assert!(report.contains("postgresql://erp_admin:********@db:5432/erp_main"));
+    }
+
+    #[test]
+    fn test_generate_preserves_manual_section() {
+        let dir = tempfile::tempdir().unwrap();
+        let app = dir.path().join("app.toml");
+        std::fs::write(&app, APP).unwrap();
+        let env_file = dir.path().join(".env");
+        let target = EnvTarget {
+            env_file: env_file.clone(),
+            compose_file: dir.path().join("docker-compose.yml"),
+            app_config: vec![app.clone()],
+            environment: "production".to_string(),
+        };
+
+        // A hand-written file: unknown keys move below the marker on the first run
+        std::fs::write(&env_file, "JWT_SECRET=stale\nSENTRY_DSN=https://key@sentry.example/1\n").unwrap();
+        let generated = generate(&target).unwrap();
+        assert_eq!(generated.manual_lines, 1);
+        assert!(generated.drift.iter().any(|d| matches!(d, EnvDrift::Changed { key, .. } if key == "JWT_SECRET")));
+
+        // Later edits below the marker survive regeneration, comments included
+        let content = std::fs::read_to_string(&env_file).unwrap();
+        std::fs::write(&env_file, format!("{}# pager integration\nPAGER_KEY='a b'\n", content)).unwrap();
+        std::fs::write(&app, APP.replace("port = 8080", "port = 9090")).unwrap();
+        let generated = generate(&target).unwrap();
+        assert_eq!(generated.value("SERVER_PORT"), Some("9090"));
+
+        let content = std::fs::read_to_string(&env_file).unwrap();
+        let manual = content.split(MANUAL_MARKER).nth(1).unwrap();
+        assert_eq!(manual, "\nSENTRY_DSN=https://key@sentry.example/1\n# pager integration\nPAGER_KEY='a b'\n");
+        let values = EnvFile::parse(&content).values();
+        assert_eq!(values["PAGER_KEY"], "a b");
+        assert_eq!(values["JWT_SECRET"], "fresh-jwt-secret-of-at-least-32-chars");
+        assert!(inspect(&target).unwrap().is_clean());
+    }
+
+    #[test]
+    fn test_check_reports_compose_variables_not_set() {
+        let compose = "services:\n  postgres:\n    environment:\n      POSTGRES_PASSWORD: ${POSTGRES_PASSWORD:-insecure}\n      \
+                       SMTP_RELAY: $SMTP_RELAY\n      # OLD: ${REMOVED}\n    healthcheck:\n      test: pg_isready -U $${POSTGRES_USER}\n";
+        assert_eq!(
+            compose_variables(compose).into_iter().collect::<Vec<_>>(),
+            vec!["POSTGRES_PASSWORD".to_string(), "SMTP_RELAY".to_string()]
+        );
+
+        let variables = variables();
+        let current = EnvFile::parse(&render(&variables, &[], &[]));
+        let report = EnvReport::build(&current, &variables, Some(compose));
+        assert_eq!(report.missing_compose, vec!["SMTP_RELAY".to_string()]);
+        let error = report.into_result(Path::new(".env")).unwrap_err();
+        assert_eq!(error.exit_code(), crate::error::exit_codes::ENV_DRIFT);
+
+        // Set by hand below the marker is good enough
+        let manual = vec!["SMTP_RELAY=mail:25".to_string()];
+        let current = EnvFile::parse(&render(&variables, &manual, &[]));
+        assert!(EnvReport::build(&current, &variables, Some(compose)).is_clean());
+    }
+}
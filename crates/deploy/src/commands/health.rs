@@ -1,10 +1,11 @@
 //! Health check command implementation
 
-use anyhow::Result;
 use colored::*;
 use serde_json::json;
 use std::time::Duration;
 use crate::config::Config;
+use crate::error::{DeployError, Result};
+use crate::network::{http_client, proxy_refused, transport_error};
 
 pub async fn execute(
     all: bool,
@@ -27,7 +28,7 @@ async fn check_all_components(format: &str, config: &Config) -> Result<()> {
     let mut results = vec![];
 
     // Check API health
-    let api_result = check_api_health(&config.monitoring.health_check_url).await;
+    let api_result = check_api_health(config).await;
     results.push(("API", api_result));
 
     // Check database
@@ -56,14 +57,17 @@ async fn check_all_components(format: &str, config: &Config) -> Result<()> {
 
 async fn check_component(component: &str, format: &str, config: &Config) -> Result<()> {
     let result = match component.to_lowercase().as_str() {
-        "api" => check_api_health(&config.monitoring.health_check_url).await,
+        "api" => check_api_health(config).await,
         "database" | "db" => check_database().await,
         "redis" => check_redis().await,
         "docker" => check_docker().await,
         "disk" => check_disk_space(&config.install_dir).await,
         "memory" | "mem" => check_memory().await,
         _ => {
-            return Err(anyhow::anyhow!("Unknown component: {}", component));
+            return Err(DeployError::invalid_input(format!(
+                "Unknown component: {}. Available: api, database, redis, docker, disk, memory",
+                component
+            )));
         }
     };
 
@@ -72,21 +76,34 @@ async fn check_component(component: &str, format: &str, config: &Config) -> Resu
 }
 
 async fn check_basic_health(format: &str, config: &Config) -> Result<()> {
-    let api_result = check_api_health(&config.monitoring.health_check_url).await;
+    let api_result = check_api_health(config).await;
     display_health_results(vec![("API", api_result)], format)?;
     Ok(())
 }
 
-async fn check_api_health(url: &str) -> HealthResult {
-    let client = reqwest::Client::new();
+/// The API's health endpoint, through the proxy and CA bundle of `[proxy]`
+async fn check_api_health(config: &Config) -> HealthResult {
+    const STEP: &str = "check API health";
+    let unhealthy = |message: String| HealthResult {
+        status: HealthStatus::Unhealthy,
+        message,
+        details: None,
+    };
+    let client = match http_client(&config.proxy) {
+        Ok(client) => client,
+        Err(e) => return unhealthy(format!("API not checked: {}", e)),
+    };
 
     match client
-        .get(url)
+        .get(&config.monitoring.health_check_url)
         .timeout(Duration::from_secs(10))
         .send()
         .await
     {
         Ok(response) => {
+            if let Some(e) = proxy_refused(&response, &config.proxy, STEP) {
+                return unhealthy(format!("API unreachable: {}", e));
+            }
             if response.status().is_success() {
                 HealthResult {
                     status: HealthStatus::Healthy,
@@ -106,10 +123,9 @@ async fn check_api_health(url: &str) -> HealthResult {
                 }
             }
         }
-        Err(e) => HealthResult {
-            status: HealthStatus::Unhealthy,
-            message: format!("API unreachable: {}", e),
-            details: None,
+        Err(e) => match transport_error(&e, &config.proxy, STEP) {
+            Some(error) => unhealthy(format!("API unreachable: {}", error)),
+            None => unhealthy(format!("API unreachable: {}", e)),
         },
     }
 }
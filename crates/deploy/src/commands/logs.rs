@@ -1,10 +1,11 @@
 //! Log management and viewing command implementations
 
-use anyhow::{anyhow, Result};
 use colored::*;
 use std::path::Path;
 use tokio::process::Command;
 
+use crate::error::{DeployError, Result};
+
 pub async fn execute(
     component: Option<&str>,
     follow: bool,
@@ -21,9 +22,10 @@ pub async fn execute(
         Some("nginx") => show_nginx_logs(follow, lines).await,
         Some("system") => show_system_logs(follow, lines, since).await,
         None => show_all_logs(follow, lines).await,
-        Some(comp) => {
-            return Err(anyhow!("Unknown component: {}. Available: docker, erp-api, postgres, redis, nginx, system", comp));
-        }
+        Some(comp) => Err(DeployError::invalid_input(format!(
+            "Unknown component: {}. Available: docker, erp-api, postgres, redis, nginx, system",
+            comp
+        ))),
     }
 }
 
@@ -69,7 +71,10 @@ async fn show_docker_logs(follow: bool, lines: usize) -> Result<()> {
             cmd.arg("--follow");
         }
 
-        let output = cmd.output().await?;
+        let output = cmd
+            .output()
+            .await
+            .map_err(|e| DeployError::from_spawn(e, "docker-compose", "read container logs"))?;
 
         if output.status.success() {
             let logs = String::from_utf8_lossy(&output.stdout);
@@ -304,7 +309,10 @@ async fn show_system_logs(follow: bool, lines: usize, since: Option<&str>) -> Re
 
             cmd.arg(log_path);
 
-            let output = cmd.output().await?;
+            let output = cmd
+                .output()
+                .await
+                .map_err(|e| DeployError::from_spawn(e, "tail", "read system log"))?;
             if output.status.success() {
                 let logs = String::from_utf8_lossy(&output.stdout);
 
@@ -365,10 +373,15 @@ async fn show_file_logs(
 
     if follow {
         // For follow mode, spawn and wait
-        let mut child = cmd.spawn()?;
+        let mut child = cmd
+            .spawn()
+            .map_err(|e| DeployError::from_spawn(e, "tail", "follow log file"))?;
         child.wait().await?;
     } else {
-        let output = cmd.output().await?;
+        let output = cmd
+            .output()
+            .await
+            .map_err(|e| DeployError::from_spawn(e, "tail", "read log file"))?;
         if output.status.success() {
             let logs = String::from_utf8_lossy(&output.stdout);
 
@@ -386,7 +399,12 @@ async fn show_file_logs(
                 print!("{}", logs);
             }
         } else {
-            return Err(anyhow!("Failed to read log file: {}", file_path));
+            return Err(DeployError::External {
+                tool: "tail".to_string(),
+                step: format!("read log file {}", file_path),
+                status: output.status.code(),
+                stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+            });
         }
     }
 
@@ -2,9 +2,19 @@
 
 pub mod install;
 pub mod tenant;
+pub mod tenant_migrate;
 pub mod database;
+pub mod db_reset;
+pub mod schema_backup;
+pub mod approval;
+pub mod command_lock;
 pub mod docker;
 pub mod health;
 pub mod backup;
+pub mod backup_transfer;
+pub mod wal_backup;
+pub mod remote_storage;
 pub mod logs;
-pub mod status;
\ No newline at end of file
+pub mod status;
+pub mod redis_doctor;
+pub mod env_file;
\ No newline at end of file
@@ -0,0 +1,904 @@
+//! Redis consistency checks (`erp-deploy redis doctor`)
+//!
+//! After a Redis failover or restore, keys can lose their TTL, outlive the
+//! tenant or user they belong to, or keep revoking tokens that expired long
+//! ago. The doctor walks the known key namespaces with `SCAN` (never `KEYS`),
+//! paced to a maximum number of keys per second, and reports per namespace:
+//! key counts, the shortest and longest remaining TTL, keys without a TTL,
+//! and keys referencing tenants or users that no longer exist in Postgres.
+//!
+//! `--repair` fixes only the cases that cannot log anyone out early or
+//! unblock a revoked token:
+//! - a missing TTL is set from the key's own data where it has any (session
+//!   expiry, revocation end), otherwise to the TTL the server uses for it
+//! - sessions of deleted tenants are deleted
+//! - revocations past the end of the token's lifetime are deleted
+//!
+//! Keys of deleted users are only reported.
+
+use chrono::{DateTime, TimeZone, Utc};
+use colored::*;
+use dialoguer::Confirm;
+use redis::aio::MultiplexedConnection;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Row};
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use super::database::connect;
+use crate::error::{DbResultExt, DeployError, RedisResultExt, Result};
+use crate::{config::Config, RedisCommands};
+
+/// Fallback session TTL when the session data cannot be read, the server's absolute session timeout
+const DEFAULT_SESSION_TTL_SECS: i64 = 12 * 3600;
+
+/// Keys listed per namespace to show where an anomaly is
+const SAMPLE_KEYS: usize = 5;
+
+/// Key namespaces the server writes to Redis
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Namespace {
+    /// `session:{tenant}:{session}`
+    Sessions,
+    /// `revoked_token:{jti}` and `token_revoked_after:{jti}`
+    Revocations,
+    /// `failed_login:{tenant}:{user}`
+    FailedLogins,
+    /// `rate_limit:{client}` and `password_change_attempts:{tenant}:{user}`
+    Counters,
+}
+
+impl Namespace {
+    pub const ALL: [Namespace; 4] = [
+        Namespace::Sessions,
+        Namespace::Revocations,
+        Namespace::FailedLogins,
+        Namespace::Counters,
+    ];
+
+    pub fn parse(name: &str) -> Result<Self> {
+        match name.trim().to_ascii_lowercase().replace('-', "_").as_str() {
+            "sessions" | "session" => Ok(Self::Sessions),
+            "revocations" | "revoked_token" => Ok(Self::Revocations),
+            "failed_logins" | "failed_login" => Ok(Self::FailedLogins),
+            "counters" => Ok(Self::Counters),
+            other => Err(DeployError::invalid_input(format!(
+                "Unknown Redis namespace '{}'; expected sessions, revocations, failed_logins or counters",
+                other
+            ))),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Sessions => "sessions",
+            Self::Revocations => "revocations",
+            Self::FailedLogins => "failed_logins",
+            Self::Counters => "counters",
+        }
+    }
+
+    fn patterns(self) -> &'static [&'static str] {
+        match self {
+            Self::Sessions => &["session:*"],
+            Self::Revocations => &["revoked_token:*", "token_revoked_after:*"],
+            Self::FailedLogins => &["failed_login:*"],
+            Self::Counters => &["rate_limit:*", "password_change_attempts:*"],
+        }
+    }
+}
+
+/// Tenant and user a key belongs to, as far as the key name tells
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct KeyOwner {
+    pub tenant_id: Option<Uuid>,
+    pub user_id: Option<Uuid>,
+}
+
+pub fn parse_key(key: &str) -> KeyOwner {
+    let parts: Vec<&str> = key.split(':').collect();
+    let uuid = |i: usize| parts.get(i).and_then(|p| Uuid::parse_str(p).ok());
+    match parts.first().copied() {
+        Some("session") => KeyOwner {
+            tenant_id: uuid(1),
+            user_id: None,
+        },
+        Some("failed_login") | Some("password_change_attempts") => KeyOwner {
+            tenant_id: uuid(1),
+            user_id: uuid(2),
+        },
+        _ => KeyOwner::default(),
+    }
+}
+
+/// Whether a tenant or user still exists
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Existence {
+    Live,
+    Deleted,
+    /// Not checked: no database, or the lookup failed
+    Unknown,
+}
+
+/// One key as found by the scan
+#[derive(Debug, Clone)]
+pub struct KeyObservation {
+    pub key: String,
+    pub namespace: Namespace,
+    /// Seconds left; `None` when the key has no TTL
+    pub ttl_secs: Option<i64>,
+    pub owner: KeyOwner,
+    /// When the key's own data says it stops mattering (session expiry, end of a revocation)
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl KeyObservation {
+    /// TTL the server gives this kind of key when it has nothing better
+    fn default_ttl_secs(&self, options: &DoctorOptions) -> i64 {
+        match self.namespace {
+            Namespace::Sessions => DEFAULT_SESSION_TTL_SECS,
+            Namespace::Revocations => options.max_token_lifetime_secs,
+            Namespace::FailedLogins => 900,
+            Namespace::Counters if self.key.starts_with("rate_limit:") => 60,
+            Namespace::Counters => 3600,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Anomaly {
+    MissingTtl,
+    DeletedTenant,
+    DeletedUser,
+    /// Still present although its own expiry has passed
+    PastExpiry,
+}
+
+pub fn classify(observation: &KeyObservation, tenant: Existence, user: Existence, now: DateTime<Utc>) -> Vec<Anomaly> {
+    let mut anomalies = Vec::new();
+    if observation.ttl_secs.is_none() {
+        anomalies.push(Anomaly::MissingTtl);
+    }
+    if tenant == Existence::Deleted {
+        anomalies.push(Anomaly::DeletedTenant);
+    }
+    if user == Existence::Deleted {
+        anomalies.push(Anomaly::DeletedUser);
+    }
+    if observation.expires_at.is_some_and(|at| at <= now) {
+        anomalies.push(Anomaly::PastExpiry);
+    }
+    anomalies
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RepairAction {
+    Delete(String),
+    ExpireIn(String, i64),
+    ExpireAt(String, i64),
+}
+
+/// The safe fix for a key's anomalies, if there is one
+pub fn repair_action(
+    observation: &KeyObservation,
+    anomalies: &[Anomaly],
+    now: DateTime<Utc>,
+    options: &DoctorOptions,
+) -> Option<RepairAction> {
+    let key = observation.key.clone();
+    let stale_session = observation.namespace == Namespace::Sessions
+        && (anomalies.contains(&Anomaly::DeletedTenant) || anomalies.contains(&Anomaly::PastExpiry));
+    let stale_revocation =
+        observation.namespace == Namespace::Revocations && anomalies.contains(&Anomaly::PastExpiry);
+    if stale_session || stale_revocation {
+        return Some(RepairAction::Delete(key));
+    }
+
+    if !anomalies.contains(&Anomaly::MissingTtl) {
+        return None;
+    }
+    match observation.expires_at {
+        Some(at) if at > now => Some(RepairAction::ExpireAt(key, at.timestamp())),
+        // Redis would delete the key on EXPIREAT in the past anyway
+        Some(_) => Some(RepairAction::Delete(key)),
+        None => Some(RepairAction::ExpireIn(key, observation.default_ttl_secs(options))),
+    }
+}
+
+/// Sleep needed after `keys` keys in `elapsed` to stay at `max_keys_per_sec`
+pub fn pacing_delay(keys: u64, elapsed: Duration, max_keys_per_sec: u32) -> Duration {
+    if max_keys_per_sec == 0 {
+        return Duration::ZERO;
+    }
+    let target = Duration::from_secs_f64(keys as f64 / max_keys_per_sec as f64);
+    target.saturating_sub(elapsed)
+}
+
+/// Tenants and users looked up in Postgres, cached for the whole scan
+pub struct IdentityCache {
+    pool: Option<PgPool>,
+    /// Live tenants map to their schema
+    tenants: HashMap<Uuid, Option<String>>,
+    users: HashMap<(Uuid, Uuid), Existence>,
+}
+
+impl IdentityCache {
+    pub fn new(pool: Option<PgPool>) -> Self {
+        Self {
+            pool,
+            tenants: HashMap::new(),
+            users: HashMap::new(),
+        }
+    }
+
+    /// A cache answering from fixed data only, without a database
+    #[cfg(test)]
+    pub fn fixed(live_tenants: &[Uuid], deleted_tenants: &[Uuid], users: &[((Uuid, Uuid), Existence)]) -> Self {
+        let mut cache = Self::new(None);
+        for tenant_id in live_tenants {
+            cache.tenants.insert(*tenant_id, Some(String::new()));
+        }
+        for tenant_id in deleted_tenants {
+            cache.tenants.insert(*tenant_id, None);
+        }
+        cache.users.extend(users.iter().copied());
+        cache
+    }
+
+    pub fn checks_enabled(&self) -> bool {
+        self.pool.is_some()
+    }
+
+    pub fn tenant(&self, tenant_id: Uuid) -> Existence {
+        match self.tenants.get(&tenant_id) {
+            Some(Some(_)) => Existence::Live,
+            Some(None) => Existence::Deleted,
+            None => Existence::Unknown,
+        }
+    }
+
+    pub fn user(&self, tenant_id: Uuid, user_id: Uuid) -> Existence {
+        // Users of a deleted tenant went with its schema
+        match self.tenant(tenant_id) {
+            Existence::Deleted => Existence::Deleted,
+            _ => self.users.get(&(tenant_id, user_id)).copied().unwrap_or(Existence::Unknown),
+        }
+    }
+
+    /// Look up the tenants and users of a batch that are not cached yet
+    async fn resolve(&mut self, owners: &[KeyOwner]) -> Result<()> {
+        let Some(pool) = self.pool.clone() else {
+            return Ok(());
+        };
+
+        let unknown_tenants: Vec<Uuid> = owners
+            .iter()
+            .filter_map(|owner| owner.tenant_id)
+            .filter(|id| !self.tenants.contains_key(id))
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        if !unknown_tenants.is_empty() {
+            let rows = sqlx::query(
+                "SELECT id, schema_name FROM public.tenants WHERE id = ANY($1) AND status <> 'deleted'",
+            )
+            .bind(&unknown_tenants)
+            .fetch_all(&pool)
+            .await
+            .db_step("look up tenants referenced by Redis keys")?;
+            for tenant_id in &unknown_tenants {
+                self.tenants.insert(*tenant_id, None);
+            }
+            for row in rows {
+                self.tenants.insert(row.try_get("id")?, Some(row.try_get("schema_name")?));
+            }
+        }
+
+        let mut unknown_users: HashMap<Uuid, HashSet<Uuid>> = HashMap::new();
+        for owner in owners {
+            if let (Some(tenant_id), Some(user_id)) = (owner.tenant_id, owner.user_id) {
+                if self.tenant(tenant_id) == Existence::Live && !self.users.contains_key(&(tenant_id, user_id)) {
+                    unknown_users.entry(tenant_id).or_default().insert(user_id);
+                }
+            }
+        }
+        for (tenant_id, user_ids) in unknown_users {
+            let Some(Some(schema)) = self.tenants.get(&tenant_id).cloned() else {
+                continue;
+            };
+            let user_ids: Vec<Uuid> = user_ids.into_iter().collect();
+            let live = match live_users(&pool, &schema, &user_ids).await {
+                Ok(live) => live,
+                Err(e) => {
+                    tracing::warn!("Could not check users of tenant {}: {}", tenant_id, e);
+                    for user_id in user_ids {
+                        self.users.insert((tenant_id, user_id), Existence::Unknown);
+                    }
+                    continue;
+                }
+            };
+            for user_id in user_ids {
+                let existence = if live.contains(&user_id) { Existence::Live } else { Existence::Deleted };
+                self.users.insert((tenant_id, user_id), existence);
+            }
+        }
+        Ok(())
+    }
+}
+
+async fn live_users(pool: &PgPool, schema: &str, user_ids: &[Uuid]) -> Result<HashSet<Uuid>> {
+    if schema.is_empty() || !schema.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return Err(DeployError::invalid_input(format!("unexpected tenant schema name '{}'", schema)));
+    }
+    let sql = format!("SELECT id FROM \"{}\".users WHERE id = ANY($1) AND deleted_at IS NULL", schema);
+    let rows = sqlx::query(&sql)
+        .bind(user_ids)
+        .fetch_all(pool)
+        .await
+        .db_step("look up users referenced by Redis keys")?;
+    rows.iter().map(|row| row.try_get("id").map_err(DeployError::from)).collect()
+}
+
+#[derive(Debug, Clone)]
+pub struct DoctorOptions {
+    pub redis_url: String,
+    pub namespaces: Vec<Namespace>,
+    pub repair: bool,
+    /// Keys asked for per `SCAN` call and commands per repair pipeline
+    pub batch_size: usize,
+    pub max_keys_per_sec: u32,
+    /// Longest token lifetime (`jwt.refresh_token_expiry`); no revocation needs to outlive it
+    pub max_token_lifetime_secs: i64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct NamespaceReport {
+    pub namespace: String,
+    pub keys: u64,
+    pub without_ttl: u64,
+    pub min_ttl_secs: Option<i64>,
+    pub max_ttl_secs: Option<i64>,
+    pub deleted_tenant: u64,
+    pub deleted_user: u64,
+    pub past_expiry: u64,
+    pub sample_keys: Vec<String>,
+}
+
+impl NamespaceReport {
+    fn record(&mut self, observation: &KeyObservation, anomalies: &[Anomaly]) {
+        self.keys += 1;
+        match observation.ttl_secs {
+            Some(ttl) => {
+                self.min_ttl_secs = Some(self.min_ttl_secs.map_or(ttl, |min| min.min(ttl)));
+                self.max_ttl_secs = Some(self.max_ttl_secs.map_or(ttl, |max| max.max(ttl)));
+            }
+            None => self.without_ttl += 1,
+        }
+        for anomaly in anomalies {
+            match anomaly {
+                Anomaly::MissingTtl => {}
+                Anomaly::DeletedTenant => self.deleted_tenant += 1,
+                Anomaly::DeletedUser => self.deleted_user += 1,
+                Anomaly::PastExpiry => self.past_expiry += 1,
+            }
+        }
+        if !anomalies.is_empty() && self.sample_keys.len() < SAMPLE_KEYS {
+            self.sample_keys.push(observation.key.clone());
+        }
+    }
+
+    pub fn anomalies(&self) -> u64 {
+        self.without_ttl + self.deleted_tenant + self.deleted_user + self.past_expiry
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct RepairSummary {
+    pub ttl_set: u64,
+    pub deleted: u64,
+    /// Keys that were gone by the time the fix was sent
+    pub skipped: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DoctorReport {
+    pub generated_at: DateTime<Utc>,
+    pub repair: bool,
+    /// Whether tenants and users were checked against Postgres
+    pub identity_checks: bool,
+    pub namespaces: Vec<NamespaceReport>,
+    pub actions: Option<RepairSummary>,
+}
+
+pub async fn execute_redis_command(
+    cmd: RedisCommands,
+    config: &Config,
+    database_url: Option<&str>,
+    assume_yes: bool,
+) -> Result<()> {
+    match cmd {
+        RedisCommands::Doctor {
+            redis_url,
+            repair,
+            namespaces,
+            format,
+            batch_size,
+            max_keys_per_sec,
+            max_token_lifetime,
+        } => {
+            let namespaces = if namespaces.is_empty() {
+                Namespace::ALL.to_vec()
+            } else {
+                namespaces.iter().map(|n| Namespace::parse(n)).collect::<Result<Vec<_>>>()?
+            };
+            let options = DoctorOptions {
+                redis_url,
+                namespaces,
+                repair,
+                batch_size: batch_size.max(1),
+                max_keys_per_sec,
+                max_token_lifetime_secs: max_token_lifetime,
+            };
+            doctor(options, config, database_url, &format, assume_yes).await
+        }
+    }
+}
+
+async fn doctor(
+    options: DoctorOptions,
+    config: &Config,
+    database_url: Option<&str>,
+    format: &str,
+    assume_yes: bool,
+) -> Result<()> {
+    if options.repair
+        && !assume_yes
+        && !Confirm::new()
+            .with_prompt("Repair Redis keys (set missing TTLs, delete stale sessions and revocations)?")
+            .interact()?
+    {
+        println!("Repair cancelled");
+        return Ok(());
+    }
+
+    let pool = match database_url.or(config.database_url.as_deref()) {
+        Some(url) => Some(connect(url).await?),
+        None => {
+            eprintln!(
+                "{}",
+                "⚠️  No database URL: keys of deleted tenants and users cannot be detected".yellow()
+            );
+            None
+        }
+    };
+    let mut identities = IdentityCache::new(pool);
+
+    let client = redis::Client::open(options.redis_url.as_str()).redis_step(&options.redis_url, "parse Redis URL")?;
+    let mut conn = client
+        .get_multiplexed_async_connection()
+        .await
+        .redis_step(&options.redis_url, "connect to Redis")?;
+
+    let report = run_doctor(&mut conn, &mut identities, &options).await?;
+
+    match format {
+        "json" => println!("{}", serde_json::to_string_pretty(&report)?),
+        "yaml" => println!("{}", serde_yaml::to_string(&report)?),
+        _ => display_report(&report),
+    }
+    Ok(())
+}
+
+/// Scan the selected namespaces, and repair them when asked
+pub async fn run_doctor(
+    conn: &mut MultiplexedConnection,
+    identities: &mut IdentityCache,
+    options: &DoctorOptions,
+) -> Result<DoctorReport> {
+    let started = Instant::now();
+    let mut scanned: u64 = 0;
+    let mut namespaces = Vec::with_capacity(options.namespaces.len());
+    let mut actions = RepairSummary::default();
+
+    for namespace in &options.namespaces {
+        let mut report = NamespaceReport {
+            namespace: namespace.name().to_string(),
+            ..Default::default()
+        };
+        for pattern in namespace.patterns() {
+            let mut cursor: u64 = 0;
+            loop {
+                let (next, keys): (u64, Vec<String>) = redis::cmd("SCAN")
+                    .arg(cursor)
+                    .arg("MATCH")
+                    .arg(*pattern)
+                    .arg("COUNT")
+                    .arg(options.batch_size)
+                    .query_async(conn)
+                    .await
+                    .redis_step(&options.redis_url, &format!("scan {}", pattern))?;
+
+                let observations = observe(conn, *namespace, keys, &options.redis_url).await?;
+                let owners: Vec<KeyOwner> = observations.iter().map(|o| o.owner).collect();
+                identities.resolve(&owners).await?;
+
+                let now = Utc::now();
+                let mut fixes = Vec::new();
+                for observation in &observations {
+                    let tenant = observation.owner.tenant_id.map_or(Existence::Unknown, |t| identities.tenant(t));
+                    let user = match (observation.owner.tenant_id, observation.owner.user_id) {
+                        (Some(tenant_id), Some(user_id)) => identities.user(tenant_id, user_id),
+                        _ => Existence::Unknown,
+                    };
+                    let anomalies = classify(observation, tenant, user, now);
+                    report.record(observation, &anomalies);
+                    if options.repair {
+                        fixes.extend(repair_action(observation, &anomalies, now, options));
+                    }
+                }
+                if !fixes.is_empty() {
+                    apply_repairs(conn, &fixes, options, &mut actions).await?;
+                }
+
+                scanned += observations.len() as u64;
+                let delay = pacing_delay(scanned, started.elapsed(), options.max_keys_per_sec);
+                if !delay.is_zero() {
+                    tokio::time::sleep(delay).await;
+                }
+
+                cursor = next;
+                if cursor == 0 {
+                    break;
+                }
+            }
+        }
+        namespaces.push(report);
+    }
+
+    Ok(DoctorReport {
+        generated_at: Utc::now(),
+        repair: options.repair,
+        identity_checks: identities.checks_enabled(),
+        namespaces,
+        actions: options.repair.then_some(actions),
+    })
+}
+
+/// Session fields the doctor needs; the server stores the full session as JSON
+#[derive(Debug, Deserialize)]
+struct StoredSession {
+    tenant_id: Uuid,
+    user_id: Uuid,
+    expires_at: DateTime<Utc>,
+}
+
+/// TTLs (and, where the value carries an expiry, values) of a scanned batch
+async fn observe(
+    conn: &mut MultiplexedConnection,
+    namespace: Namespace,
+    keys: Vec<String>,
+    redis_url: &str,
+) -> Result<Vec<KeyObservation>> {
+    if keys.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut pipe = redis::pipe();
+    for key in &keys {
+        pipe.cmd("TTL").arg(key);
+    }
+    let ttls: Vec<i64> = pipe.query_async(conn).await.redis_step(redis_url, "read key TTLs")?;
+
+    let values: Vec<Option<String>> = match namespace {
+        Namespace::Sessions | Namespace::Revocations => {
+            let mut pipe = redis::pipe();
+            for key in &keys {
+                pipe.cmd("GET").arg(key);
+            }
+            pipe.query_async(conn).await.redis_step(redis_url, "read key values")?
+        }
+        Namespace::FailedLogins | Namespace::Counters => vec![None; keys.len()],
+    };
+
+    let observations = keys
+        .into_iter()
+        .zip(ttls)
+        .zip(values)
+        // -2: the key expired between SCAN and TTL
+        .filter(|((_, ttl), _)| *ttl != -2)
+        .map(|((key, ttl), value)| {
+            let mut owner = parse_key(&key);
+            let expires_at = match namespace {
+                Namespace::Sessions => value
+                    .and_then(|v| serde_json::from_str::<StoredSession>(&v).ok())
+                    .map(|session| {
+                        owner.tenant_id = owner.tenant_id.or(Some(session.tenant_id));
+                        owner.user_id = Some(session.user_id);
+                        session.expires_at
+                    }),
+                // Revocations store when they stop mattering; older entries store "1"
+                Namespace::Revocations => value
+                    .and_then(|v| v.parse::<i64>().ok())
+                    .filter(|ts| *ts > 1)
+                    .and_then(|ts| Utc.timestamp_opt(ts, 0).single()),
+                Namespace::FailedLogins | Namespace::Counters => None,
+            };
+            KeyObservation {
+                key,
+                namespace,
+                ttl_secs: (ttl >= 0).then_some(ttl),
+                owner,
+                expires_at,
+            }
+        })
+        .collect();
+    Ok(observations)
+}
+
+async fn apply_repairs(
+    conn: &mut MultiplexedConnection,
+    fixes: &[RepairAction],
+    options: &DoctorOptions,
+    summary: &mut RepairSummary,
+) -> Result<()> {
+    for chunk in fixes.chunks(options.batch_size) {
+        let mut pipe = redis::pipe();
+        for fix in chunk {
+            match fix {
+                RepairAction::Delete(key) => pipe.cmd("DEL").arg(key),
+                RepairAction::ExpireIn(key, secs) => pipe.cmd("EXPIRE").arg(key).arg(*secs),
+                RepairAction::ExpireAt(key, at) => pipe.cmd("EXPIREAT").arg(key).arg(*at),
+            };
+        }
+        let results: Vec<i64> = pipe
+            .query_async(conn)
+            .await
+            .redis_step(&options.redis_url, "apply Redis repairs")?;
+
+        for (fix, applied) in chunk.iter().zip(results) {
+            match (fix, applied > 0) {
+                (_, false) => summary.skipped += 1,
+                (RepairAction::Delete(_), true) => summary.deleted += 1,
+                (_, true) => summary.ttl_set += 1,
+            }
+        }
+    }
+    Ok(())
+}
+
+fn display_report(report: &DoctorReport) {
+    println!("{}", "🩺 Redis Doctor".blue().bold());
+    println!("{}", "=".repeat(96));
+    if !report.identity_checks {
+        println!("{}", "Tenant and user checks skipped (no database)".yellow());
+    }
+    println!(
+        "{:<15} {:>9} {:>11} {:>10} {:>10} {:>12} {:>11} {:>11}",
+        "Namespace", "Keys", "Without TTL", "Min TTL", "Max TTL", "Del. tenant", "Del. user", "Past expiry"
+    );
+    println!("{}", "-".repeat(96));
+
+    let ttl = |secs: Option<i64>| secs.map(|s| format!("{}s", s)).unwrap_or_else(|| "-".to_string());
+    for ns in &report.namespaces {
+        let line = format!(
+            "{:<15} {:>9} {:>11} {:>10} {:>10} {:>12} {:>11} {:>11}",
+            ns.namespace,
+            ns.keys,
+            ns.without_ttl,
+            ttl(ns.min_ttl_secs),
+            ttl(ns.max_ttl_secs),
+            ns.deleted_tenant,
+            ns.deleted_user,
+            ns.past_expiry
+        );
+        if ns.anomalies() > 0 {
+            println!("{}", line.yellow());
+        } else {
+            println!("{}", line);
+        }
+    }
+
+    for ns in report.namespaces.iter().filter(|ns| !ns.sample_keys.is_empty()) {
+        println!();
+        println!("{} {}:", "Examples in".bold(), ns.namespace);
+        for key in &ns.sample_keys {
+            println!("  {}", key);
+        }
+    }
+
+    if let Some(actions) = &report.actions {
+        println!();
+        println!(
+            "Repaired: {} TTLs set, {} keys deleted, {} skipped",
+            actions.ttl_set.to_string().green(),
+            actions.deleted.to_string().green(),
+            actions.skipped
+        );
+    } else if report.namespaces.iter().any(|ns| ns.anomalies() > 0) {
+        println!();
+        println!("Run with --repair to fix missing TTLs, sessions of deleted tenants and expired revocations");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration as ChronoDuration;
+
+    fn options() -> DoctorOptions {
+        DoctorOptions {
+            redis_url: std::env::var("ERP_TEST_REDIS_URL").unwrap_or_else(|_| "redis://localhost:6379/15".to_string()),
+            namespaces: Namespace::ALL.to_vec(),
+            repair: false,
+            batch_size: 10,
+            max_keys_per_sec: 0,
+            max_token_lifetime_secs: 2_592_000,
+        }
+    }
+
+    fn observation(key: &str, namespace: Namespace, ttl_secs: Option<i64>, expires_at: Option<DateTime<Utc>>) -> KeyObservation {
+        KeyObservation {
+            key: key.to_string(),
+            namespace,
+            ttl_secs,
+            owner: parse_key(key),
+            expires_at,
+        }
+    }
+
+    #[test]
+    fn test_parse_key_owners() {
+        let tenant = Uuid::new_v4();
+        let user = Uuid::new_v4();
+        assert_eq!(parse_key(&format!("session:{}:abc", tenant)).tenant_id, Some(tenant));
+        assert_eq!(
+            parse_key(&format!("failed_login:{}:{}", tenant, user)),
+            KeyOwner { tenant_id: Some(tenant), user_id: Some(user) }
+        );
+        assert_eq!(parse_key("rate_limit:10.0.0.1"), KeyOwner::default());
+        assert_eq!(parse_key("revoked_token:whatever"), KeyOwner::default());
+    }
+
+    #[test]
+    fn test_namespace_filter_parsing() {
+        assert_eq!(Namespace::parse("failed-logins").unwrap(), Namespace::FailedLogins);
+        assert_eq!(Namespace::parse("Sessions").unwrap(), Namespace::Sessions);
+        assert!(Namespace::parse("carts").is_err());
+    }
+
+    #[test]
+    fn test_missing_ttl_uses_key_data_then_server_default() {
+        let now = Utc::now();
+        let expires = now + ChronoDuration::hours(2);
+        let session = observation("session:t:s", Namespace::Sessions, None, Some(expires));
+        let anomalies = classify(&session, Existence::Live, Existence::Unknown, now);
+        assert_eq!(anomalies, vec![Anomaly::MissingTtl]);
+        assert_eq!(
+            repair_action(&session, &anomalies, now, &options()),
+            Some(RepairAction::ExpireAt("session:t:s".to_string(), expires.timestamp()))
+        );
+
+        let counter = observation("failed_login:t:u", Namespace::FailedLogins, None, None);
+        let anomalies = classify(&counter, Existence::Unknown, Existence::Unknown, now);
+        assert_eq!(
+            repair_action(&counter, &anomalies, now, &options()),
+            Some(RepairAction::ExpireIn("failed_login:t:u".to_string(), 900))
+        );
+
+        let legacy_revocation = observation("revoked_token:j", Namespace::Revocations, None, None);
+        let anomalies = classify(&legacy_revocation, Existence::Unknown, Existence::Unknown, now);
+        assert_eq!(
+            repair_action(&legacy_revocation, &anomalies, now, &options()),
+            Some(RepairAction::ExpireIn("revoked_token:j".to_string(), 2_592_000))
+        );
+    }
+
+    #[test]
+    fn test_only_safe_cases_are_deleted() {
+        let now = Utc::now();
+        let tenant = Uuid::new_v4();
+        let session = observation(&format!("session:{}:s", tenant), Namespace::Sessions, Some(600), None);
+        let anomalies = classify(&session, Existence::Deleted, Existence::Unknown, now);
+        assert!(matches!(repair_action(&session, &anomalies, now, &options()), Some(RepairAction::Delete(_))));
+
+        let expired = observation("revoked_token:j", Namespace::Revocations, Some(60), Some(now - ChronoDuration::seconds(1)));
+        let anomalies = classify(&expired, Existence::Unknown, Existence::Unknown, now);
+        assert_eq!(anomalies, vec![Anomaly::PastExpiry]);
+        assert!(matches!(repair_action(&expired, &anomalies, now, &options()), Some(RepairAction::Delete(_))));
+
+        // Counters of deleted users are reported, not touched
+        let counter = observation("failed_login:t:u", Namespace::FailedLogins, Some(300), None);
+        let anomalies = classify(&counter, Existence::Live, Existence::Deleted, now);
+        assert_eq!(anomalies, vec![Anomaly::DeletedUser]);
+        assert_eq!(repair_action(&counter, &anomalies, now, &options()), None);
+    }
+
+    #[test]
+    fn test_pacing_delay() {
+        assert_eq!(pacing_delay(500, Duration::from_millis(200), 1000), Duration::from_millis(300));
+        assert_eq!(pacing_delay(500, Duration::from_secs(1), 1000), Duration::ZERO);
+        assert_eq!(pacing_delay(500, Duration::ZERO, 0), Duration::ZERO);
+    }
+
+    /// Seeds every anomaly into a scratch Redis database (`ERP_TEST_REDIS_URL`,
+    /// default db 15 on localhost) and checks both the report and the repair.
+    #[tokio::test]
+    #[ignore = "requires Redis"]
+    async fn test_doctor_against_seeded_redis() {
+        use redis::AsyncCommands;
+
+        let mut options = options();
+        let client = redis::Client::open(options.redis_url.as_str()).unwrap();
+        let mut conn = client.get_multiplexed_async_connection().await.unwrap();
+        let _: () = redis::cmd("FLUSHDB").query_async(&mut conn).await.unwrap();
+
+        let live_tenant = Uuid::new_v4();
+        let deleted_tenant = Uuid::new_v4();
+        let live_user = Uuid::new_v4();
+        let deleted_user = Uuid::new_v4();
+        let now = Utc::now();
+        let session = |tenant: Uuid, expires_at: DateTime<Utc>| {
+            serde_json::json!({ "tenant_id": tenant, "user_id": live_user, "expires_at": expires_at }).to_string()
+        };
+
+        // Healthy keys
+        let _: () = conn
+            .set_ex(format!("session:{}:ok", live_tenant), session(live_tenant, now + ChronoDuration::hours(1)), 3600)
+            .await
+            .unwrap();
+        let _: () = conn.set_ex(format!("failed_login:{}:{}", live_tenant, live_user), 2, 900).await.unwrap();
+        // Session without TTL
+        let _: () = conn
+            .set(format!("session:{}:no-ttl", live_tenant), session(live_tenant, now + ChronoDuration::hours(1)))
+            .await
+            .unwrap();
+        // Session of a deleted tenant
+        let _: () = conn
+            .set_ex(format!("session:{}:orphan", deleted_tenant), session(deleted_tenant, now + ChronoDuration::hours(1)), 3600)
+            .await
+            .unwrap();
+        // Revocation past its token's lifetime, and a legacy one without TTL
+        let _: () = conn.set("revoked_token:expired", (now - ChronoDuration::hours(1)).timestamp()).await.unwrap();
+        let _: () = conn.set("revoked_token:legacy", "1").await.unwrap();
+        // Counter of a deleted user
+        let _: () = conn.set_ex(format!("failed_login:{}:{}", live_tenant, deleted_user), 3, 900).await.unwrap();
+
+        let users = [
+            ((live_tenant, live_user), Existence::Live),
+            ((live_tenant, deleted_user), Existence::Deleted),
+        ];
+        let mut identities = IdentityCache::fixed(&[live_tenant], &[deleted_tenant], &users);
+
+        let report = run_doctor(&mut conn, &mut identities, &options).await.unwrap();
+        let by_name: HashMap<&str, &NamespaceReport> = report.namespaces.iter().map(|ns| (ns.namespace.as_str(), ns)).collect();
+        assert_eq!(by_name["sessions"].keys, 3);
+        assert_eq!(by_name["sessions"].without_ttl, 1);
+        assert_eq!(by_name["sessions"].deleted_tenant, 1);
+        assert_eq!(by_name["revocations"].past_expiry, 1);
+        assert_eq!(by_name["revocations"].without_ttl, 2);
+        assert_eq!(by_name["failed_logins"].deleted_user, 1);
+        assert!(report.actions.is_none());
+
+        options.repair = true;
+        let report = run_doctor(&mut conn, &mut identities, &options).await.unwrap();
+        let actions = report.actions.unwrap();
+        assert_eq!(actions.deleted, 2);
+        assert_eq!(actions.ttl_set, 2);
+
+        let orphan: bool = conn.exists(format!("session:{}:orphan", deleted_tenant)).await.unwrap();
+        let expired: bool = conn.exists("revoked_token:expired").await.unwrap();
+        let legacy_ttl: i64 = conn.ttl("revoked_token:legacy").await.unwrap();
+        let deleted_user_counter: bool = conn.exists(format!("failed_login:{}:{}", live_tenant, deleted_user)).await.unwrap();
+        assert!(!orphan && !expired);
+        assert!(legacy_ttl > 0);
+        assert!(deleted_user_counter);
+
+        options.repair = false;
+        let report = run_doctor(&mut conn, &mut identities, &options).await.unwrap();
+        assert!(report.namespaces.iter().all(|ns| ns.without_ttl == 0 && ns.deleted_tenant == 0 && ns.past_expiry == 0));
+    }
+}
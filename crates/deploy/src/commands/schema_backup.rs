@@ -0,0 +1,361 @@
+//! Database dumps with manifests (`erp-deploy database backup` / `restore`)
+//!
+//! `database backup <name>` dumps the whole database; with `--tenant` only
+//! that tenant's schema and the shared tables its tables reference through
+//! foreign keys. The dump is written in pg_dump's custom format, compressed
+//! as `--compression` says:
+//!
+//! - `gzip`: `<name>_<timestamp>.dump.gz`
+//! - `bzip2`: `<name>_<timestamp>.dump.bz2`
+//! - `none`: `<name>_<timestamp>.dump`
+//!
+//! Next to it, `<dump>.manifest.json` records the tenant and schema, the
+//! migration version of the database at backup time, and size and SHA-256 of
+//! the dump file as written, compressed or not.
+//!
+//! `database restore <dump>` checks the dump against its manifest and refuses
+//! a backup taken at a newer migration version than the target database has,
+//! before it asks for confirmation or touches anything. A tenant backup only
+//! restores the tenant's schema; the shared tables in it are there to be
+//! restored by hand with `pg_restore --table` where needed, since restoring
+//! them would overwrite every other tenant's rows.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::{PgPool, Row};
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use super::db_reset::validate_identifier;
+use crate::error::{DbResultExt, DeployError, IoResultExt, Result};
+
+const MANIFEST_SUFFIX: &str = ".manifest.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DumpCompression {
+    Gzip,
+    Bzip2,
+    None,
+}
+
+impl DumpCompression {
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "gzip" => Ok(Self::Gzip),
+            "bzip2" => Ok(Self::Bzip2),
+            "none" => Ok(Self::None),
+            other => Err(DeployError::invalid_input(format!(
+                "Unknown compression '{}'; use gzip, bzip2 or none",
+                other
+            ))),
+        }
+    }
+
+    /// Appended to the dump file name
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Gzip => ".gz",
+            Self::Bzip2 => ".bz2",
+            Self::None => "",
+        }
+    }
+
+    /// The tool that compresses and decompresses dumps; `None` for plain dumps
+    pub fn tool(self) -> Option<&'static str> {
+        match self {
+            Self::Gzip => Some("gzip"),
+            Self::Bzip2 => Some("bzip2"),
+            Self::None => None,
+        }
+    }
+}
+
+/// Written next to every dump
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DumpManifest {
+    /// File name of the dump
+    pub backup: String,
+    /// The tenant of a tenant backup; `None` for the whole database
+    pub tenant_id: Option<Uuid>,
+    pub schema_name: Option<String>,
+    /// Shared tables dumped along with the tenant's schema, e.g. `public.tenants`
+    #[serde(default)]
+    pub shared_tables: Vec<String>,
+    /// Latest migration applied to the database at backup time
+    pub migration_version: Option<i64>,
+    pub compression: DumpCompression,
+    /// Size of the dump file in bytes
+    pub size: u64,
+    /// SHA-256 of the dump file, hex encoded
+    pub sha256: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl DumpManifest {
+    /// The dump must be the file the manifest was written for
+    pub fn verify_dump(&self, dump: &Path) -> Result<()> {
+        let (size, sha256) = file_checksum(dump)?;
+        if size != self.size || sha256 != self.sha256 {
+            return Err(DeployError::invalid_input(format!(
+                "{} does not match its manifest: {} bytes with SHA-256 {}, expected {} bytes with SHA-256 {}",
+                dump.display(),
+                size,
+                sha256,
+                self.size,
+                self.sha256
+            )));
+        }
+        Ok(())
+    }
+
+    /// A backup may only be restored into a database migrated at least as far
+    pub fn check_target(&self, target_version: Option<i64>) -> Result<()> {
+        match (self.migration_version, target_version) {
+            (Some(backup), target) if target.is_none_or(|target| target < backup) => {
+                Err(DeployError::invalid_input(format!(
+                    "The backup was taken at migration {} but the target database is at {}; migrate it first",
+                    backup,
+                    target.map(|t| t.to_string()).unwrap_or_else(|| "none".to_string())
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// `<name>_<timestamp>.dump` with the extension of the compression
+pub fn dump_file_name(name: &str, compression: DumpCompression, created_at: DateTime<Utc>) -> Result<String> {
+    let valid = !name.is_empty()
+        && !name.starts_with('.')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'));
+    if !valid {
+        return Err(DeployError::invalid_input(format!("Invalid backup name: {}", name)));
+    }
+    Ok(format!(
+        "{}_{}.dump{}",
+        name,
+        created_at.format("%Y%m%d_%H%M%S"),
+        compression.extension()
+    ))
+}
+
+/// Where the manifest of `dump` is kept
+pub fn manifest_path(dump: &Path) -> PathBuf {
+    let mut name = dump.file_name().unwrap_or_default().to_os_string();
+    name.push(MANIFEST_SUFFIX);
+    dump.with_file_name(name)
+}
+
+pub fn write_manifest(dump: &Path, manifest: &DumpManifest) -> Result<PathBuf> {
+    let path = manifest_path(dump);
+    let content = serde_json::to_string_pretty(manifest).map_err(|e| DeployError::internal("write backup manifest", e))?;
+    fs::write(&path, content).io_step(&path, "write backup manifest")?;
+    Ok(path)
+}
+
+/// The manifest of `dump`; a dump without one is not restored
+pub fn read_manifest(dump: &Path) -> Result<DumpManifest> {
+    let path = manifest_path(dump);
+    if !path.exists() {
+        return Err(DeployError::config(
+            Some(&path),
+            format!("{} has no manifest; only backups taken by `database backup` can be restored", dump.display()),
+        ));
+    }
+    let content = fs::read_to_string(&path).io_step(&path, "read backup manifest")?;
+    serde_json::from_str(&content).map_err(|e| DeployError::Config {
+        path: Some(path.clone()),
+        message: "invalid backup manifest".to_string(),
+        source: Some(Box::new(e)),
+    })
+}
+
+/// Size and SHA-256 of a file
+pub fn file_checksum(path: &Path) -> Result<(u64, String)> {
+    const STEP: &str = "checksum backup";
+    let mut file = fs::File::open(path).io_step(path, STEP)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 1 << 20];
+    let mut size = 0u64;
+    loop {
+        let read = file.read(&mut buffer).io_step(path, STEP)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+        size += read as u64;
+    }
+    Ok((size, hex::encode(hasher.finalize())))
+}
+
+/// A tenant and the schema holding its data
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TenantSchema {
+    pub tenant_id: Uuid,
+    pub schema: String,
+}
+
+/// Look up a tenant by ID, name or schema
+pub async fn find_tenant(pool: &PgPool, tenant: &str) -> Result<TenantSchema> {
+    let row = sqlx::query("SELECT id, schema_name FROM public.tenants WHERE id::text = $1 OR schema_name = $1 OR name = $1")
+        .bind(tenant)
+        .fetch_optional(pool)
+        .await
+        .db_step("look up tenant")?
+        .ok_or_else(|| DeployError::tenant_not_found(tenant))?;
+    let schema: String = row
+        .get::<Option<String>, _>("schema_name")
+        .ok_or_else(|| DeployError::invalid_input(format!("Tenant {} has no schema", tenant)))?;
+    validate_identifier(&schema)?;
+    if schema == "public" {
+        return Err(DeployError::invalid_input(format!("Tenant {} uses the shared schema", tenant)));
+    }
+    Ok(TenantSchema {
+        tenant_id: row.get("id"),
+        schema,
+    })
+}
+
+/// Tables of other schemas the tables of `schema` reference through foreign keys
+pub async fn shared_tables(pool: &PgPool, schema: &str) -> Result<Vec<String>> {
+    sqlx::query_scalar(
+        "SELECT DISTINCT rn.nspname || '.' || r.relname \
+         FROM pg_constraint c \
+         JOIN pg_class t ON t.oid = c.conrelid \
+         JOIN pg_namespace tn ON tn.oid = t.relnamespace \
+         JOIN pg_class r ON r.oid = c.confrelid \
+         JOIN pg_namespace rn ON rn.oid = r.relnamespace \
+         WHERE c.contype = 'f' AND tn.nspname = $1 AND rn.nspname <> $1 \
+         ORDER BY 1",
+    )
+    .bind(schema)
+    .fetch_all(pool)
+    .await
+    .db_step("list shared tables")
+}
+
+/// Latest migration applied to the database; `None` before the first
+pub async fn migration_version(pool: &PgPool) -> Result<Option<i64>> {
+    let migrated: bool = sqlx::query_scalar("SELECT to_regclass('public._sqlx_migrations') IS NOT NULL")
+        .fetch_one(pool)
+        .await
+        .db_step("check migration table")?;
+    if !migrated {
+        return Ok(None);
+    }
+    sqlx::query_scalar("SELECT MAX(version) FROM public._sqlx_migrations WHERE success")
+        .fetch_one(pool)
+        .await
+        .db_step("read migration version")
+}
+
+/// pg_dump arguments selecting a tenant's schema and the shared tables it references
+///
+/// `--schema` alone would leave out the tables of the schema once `--table`
+/// names the shared ones, so its tables are named by pattern as well.
+pub fn tenant_dump_selection(schema: &str, shared_tables: &[String]) -> Vec<String> {
+    let mut args = vec![
+        "--schema".to_string(),
+        schema.to_string(),
+        "--table".to_string(),
+        format!("{}.*", schema),
+    ];
+    for table in shared_tables {
+        args.push("--table".to_string());
+        args.push(table.clone());
+    }
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn manifest(dump: &Path, migration_version: Option<i64>) -> DumpManifest {
+        let (size, sha256) = file_checksum(dump).unwrap();
+        DumpManifest {
+            backup: dump.file_name().unwrap().to_string_lossy().into_owned(),
+            tenant_id: Some(Uuid::new_v4()),
+            schema_name: Some("tenant_acme_corp".to_string()),
+            shared_tables: vec!["public.tenants".to_string()],
+            migration_version,
+            compression: DumpCompression::Gzip,
+            size,
+            sha256,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_dump_names_follow_the_compression() {
+        let at = Utc.with_ymd_and_hms(2026, 10, 17, 2, 0, 0).unwrap();
+        assert_eq!(
+            dump_file_name("acme", DumpCompression::parse("GZIP").unwrap(), at).unwrap(),
+            "acme_20261017_020000.dump.gz"
+        );
+        assert_eq!(
+            dump_file_name("acme", DumpCompression::Bzip2, at).unwrap(),
+            "acme_20261017_020000.dump.bz2"
+        );
+        assert_eq!(dump_file_name("acme", DumpCompression::None, at).unwrap(), "acme_20261017_020000.dump");
+        assert!(dump_file_name("../acme", DumpCompression::None, at).is_err());
+        assert!(DumpCompression::parse("zstd").is_err());
+        assert_eq!(
+            manifest_path(Path::new("/backups/acme_20261017_020000.dump.gz")),
+            Path::new("/backups/acme_20261017_020000.dump.gz.manifest.json")
+        );
+    }
+
+    #[test]
+    fn test_manifest_round_trip_and_checksum() {
+        let dir = tempfile::tempdir().unwrap();
+        let dump = dir.path().join("acme_20261017_020000.dump.gz");
+        fs::write(&dump, b"compressed dump").unwrap();
+        let written = manifest(&dump, Some(52));
+        write_manifest(&dump, &written).unwrap();
+
+        let read = read_manifest(&dump).unwrap();
+        assert_eq!(read, written);
+        assert_eq!(read.size, 15);
+        read.verify_dump(&dump).unwrap();
+
+        // A dump changed after the backup is refused
+        fs::write(&dump, b"compressed dumP").unwrap();
+        assert!(read.verify_dump(&dump).is_err());
+
+        let other = dir.path().join("other.dump");
+        fs::write(&other, b"no manifest").unwrap();
+        assert!(read_manifest(&other).is_err());
+    }
+
+    #[test]
+    fn test_backups_of_newer_migrations_are_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let dump = dir.path().join("acme.dump");
+        fs::write(&dump, b"dump").unwrap();
+        let manifest = manifest(&dump, Some(52));
+
+        assert!(manifest.check_target(Some(52)).is_ok());
+        assert!(manifest.check_target(Some(60)).is_ok());
+        assert!(manifest.check_target(Some(51)).is_err());
+        assert!(manifest.check_target(None).is_err());
+        let unmigrated = DumpManifest {
+            migration_version: None,
+            ..manifest
+        };
+        assert!(unmigrated.check_target(None).is_ok());
+    }
+
+    #[test]
+    fn test_tenant_dump_selection() {
+        assert_eq!(
+            tenant_dump_selection("tenant_acme", &["public.tenants".to_string()]),
+            vec!["--schema", "tenant_acme", "--table", "tenant_acme.*", "--table", "public.tenants"]
+        );
+    }
+}
@@ -2,11 +2,19 @@
 
 use std::collections::HashMap;
 use std::process::Command;
-use anyhow::Result;
 use chrono::{DateTime, Utc};
 use colored::Colorize;
+use erp_core::effective_config::ValueSource;
+use erp_core::jobs::{JobQueue, QueueHealth, QueueHealthEvaluator};
+use erp_core::{JobHealthConfig, RedisJobQueue};
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
+use std::time::Duration;
+
+use crate::build_info::{check_compatibility, current_build, BuildInfo, Compatibility};
+use crate::config::Config;
+use crate::error::{DeployError, IoResultExt, Result};
+use crate::network::{http_client, proxy_refused, transport_error};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SystemStatus {
@@ -15,6 +23,7 @@ pub struct SystemStatus {
     pub timestamp: DateTime<Utc>,
     pub uptime: Option<String>,
     pub version: String,
+    pub build: BuildInfo,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -39,16 +48,18 @@ pub async fn execute(
     detailed: bool,
     format: String,
     component: Option<String>,
+    config: &Config,
 ) -> Result<()> {
-    show_status(detailed, format, component).await
+    show_status(detailed, format, component, config).await
 }
 
 pub async fn show_status(
     detailed: bool,
     format: String,
     component: Option<String>,
+    config: &Config,
 ) -> Result<()> {
-    let status = collect_system_status(detailed).await?;
+    let status = collect_system_status(detailed, config).await?;
 
     match format.as_str() {
         "json" => {
@@ -65,13 +76,97 @@ pub async fn show_status(
     Ok(())
 }
 
-async fn collect_system_status(detailed: bool) -> Result<SystemStatus> {
+/// Show the effective configuration this directory and environment resolve
+/// to, as the API server records it at startup, and whether the running API
+/// reports the same configuration hash
+pub async fn show_effective_config(format: String, config: &Config) -> Result<()> {
+    let (_, effective) = erp_core::Config::load_effective()
+        .map_err(|e| DeployError::config(None, format!("Configuration does not load: {}", e)))?;
+    let api_hash = fetch_api_config_hash(config).await;
+    let matches_api = api_hash.as_deref().map(|hash| effective.matches(hash));
+
+    match format.as_str() {
+        "json" | "yaml" => {
+            let report = serde_json::json!({
+                "effective_config": effective,
+                "api_config_hash": api_hash,
+                "matches_api": matches_api,
+            });
+            if format == "json" {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                println!("{}", serde_yaml::to_string(&report)?);
+            }
+        }
+        _ => display_effective_config(&effective, api_hash.as_deref()),
+    }
+
+    Ok(())
+}
+
+/// `config_hash` of the API's version endpoint, next to its health check
+async fn fetch_api_config_hash(config: &Config) -> Option<String> {
+    let url = url::Url::parse(&config.monitoring.health_check_url)
+        .and_then(|health| health.join("/api/v1/meta/version"))
+        .ok()?;
+    let client = http_client(&config.proxy).ok()?;
+    let response = match client.get(url).timeout(Duration::from_secs(10)).send().await {
+        Ok(response) if response.status().is_success() => response,
+        Ok(response) => {
+            tracing::debug!("API version endpoint returned status: {}", response.status());
+            return None;
+        }
+        Err(e) => {
+            tracing::debug!("API version endpoint unreachable: {}", e);
+            return None;
+        }
+    };
+    let body = response.json::<serde_json::Value>().await.ok()?;
+    body.get("config_hash")?.as_str().map(str::to_string)
+}
+
+fn display_effective_config(effective: &erp_core::EffectiveConfig, api_hash: Option<&str>) {
+    println!("\n{}", "Effective Configuration".bold());
+    println!("Environment: {}", effective.environment);
+    for file in &effective.files {
+        println!("File: {} (sha256 {})", file.path, file.sha256);
+    }
+    if effective.files.is_empty() {
+        println!("File: none, defaults and environment variables only");
+    }
+    println!("Config hash: {}", effective.config_hash);
+    match api_hash {
+        Some(hash) if effective.matches(hash) => println!("API: {}", "runs with this configuration".green()),
+        Some(hash) => println!("API: {}", format!("runs with a different configuration ({})", hash).yellow()),
+        None => println!("API: {}", "does not report its configuration hash".cyan()),
+    }
+    println!();
+
+    println!("{:<55} {:<30} Value", "Setting", "Source");
+    println!("{}", "-".repeat(110));
+    for value in &effective.values {
+        let source = match &value.source {
+            ValueSource::Default => "default".to_string(),
+            ValueSource::File { path } => path.clone(),
+            ValueSource::Env { var } => format!("env {}", var),
+        };
+        println!("{:<55} {:<30} {}", value.key, source, value.value);
+    }
+}
+
+async fn collect_system_status(detailed: bool, config: &Config) -> Result<SystemStatus> {
     let mut components = HashMap::new();
     let start_time = std::time::Instant::now();
 
+    // Check the API server and compare its build with this CLI
+    components.insert("api".to_string(), check_api(config).await);
+
     // Check database connectivity
     components.insert("database".to_string(), check_database().await);
 
+    // Check job queue consumers and backlog
+    components.insert("jobs".to_string(), check_job_queues().await);
+
     // Check Docker service
     components.insert("docker".to_string(), check_docker().await);
 
@@ -98,9 +193,89 @@ async fn collect_system_status(detailed: bool) -> Result<SystemStatus> {
         timestamp: Utc::now(),
         uptime: get_system_uptime().await.ok(),
         version: env!("CARGO_PKG_VERSION").to_string(),
+        build: current_build(),
     })
 }
 
+async fn check_api(config: &Config) -> ComponentStatus {
+    const STEP: &str = "check API status";
+    let start = std::time::Instant::now();
+    let critical = |message: String| ComponentStatus {
+        status: HealthStatus::Critical,
+        message,
+        details: None,
+        last_check: Utc::now(),
+        response_time_ms: Some(start.elapsed().as_millis() as u64),
+    };
+    let client = match http_client(&config.proxy) {
+        Ok(client) => client,
+        Err(e) => return critical(format!("API not checked: {}", e)),
+    };
+
+    let response = match client.get(&config.monitoring.health_check_url).timeout(Duration::from_secs(10)).send().await {
+        Ok(response) if response.status().is_success() => response,
+        Ok(response) => {
+            return match proxy_refused(&response, &config.proxy, STEP) {
+                Some(e) => critical(format!("API unreachable: {}", e)),
+                None => critical(format!("API returned status: {}", response.status())),
+            };
+        }
+        Err(e) => {
+            return match transport_error(&e, &config.proxy, STEP) {
+                Some(error) => critical(format!("API unreachable: {}", error)),
+                None => critical(format!("API unreachable: {}", e)),
+            };
+        }
+    };
+
+    let api_build = response
+        .json::<serde_json::Value>()
+        .await
+        .ok()
+        .and_then(|body| serde_json::from_value::<BuildInfo>(body.get("build")?.clone()).ok());
+
+    let (status, message) = compare_builds(api_build.as_ref(), &current_build());
+
+    ComponentStatus {
+        status,
+        message,
+        details: Some(serde_json::json!({
+            "api_build": api_build,
+            "cli_build": current_build(),
+        })),
+        last_check: Utc::now(),
+        response_time_ms: Some(start.elapsed().as_millis() as u64),
+    }
+}
+
+/// Status of a running API build as seen from this CLI build
+fn compare_builds(api: Option<&BuildInfo>, cli: &BuildInfo) -> (HealthStatus, String) {
+    let Some(api) = api else {
+        return (HealthStatus::Warning, "API responding but does not report its build".to_string());
+    };
+
+    match check_compatibility(&cli.version, &api.version) {
+        Compatibility::Incompatible { supported } => (
+            HealthStatus::Warning,
+            format!(
+                "API {} is not supported by CLI {} (supports API {})",
+                api.label(),
+                cli.label(),
+                supported.join(", ")
+            ),
+        ),
+        Compatibility::Unknown => (
+            HealthStatus::Warning,
+            format!("Compatibility of API {} with CLI {} is unknown", api.label(), cli.label()),
+        ),
+        Compatibility::Compatible if api.commit != cli.commit => (
+            HealthStatus::Healthy,
+            format!("API {} responding (CLI is {})", api.label(), cli.label()),
+        ),
+        Compatibility::Compatible => (HealthStatus::Healthy, format!("API {} responding", api.label())),
+    }
+}
+
 async fn check_database() -> ComponentStatus {
     let start = std::time::Instant::now();
 
@@ -146,6 +321,57 @@ async fn check_database() -> ComponentStatus {
     }
 }
 
+/// Depth, oldest job age and heartbeat freshness of the job queues. A single
+/// look cannot tell a growing backlog; only missing or stale heartbeats count.
+async fn check_job_queues() -> ComponentStatus {
+    let start = std::time::Instant::now();
+    let redis_url = std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://localhost:6379".to_string());
+
+    let connection = match redis::Client::open(redis_url.as_str()) {
+        Ok(client) => redis::aio::ConnectionManager::new(client).await,
+        Err(e) => Err(e),
+    };
+    let connection = match connection {
+        Ok(connection) => connection,
+        Err(e) => {
+            return ComponentStatus {
+                status: HealthStatus::Critical,
+                message: format!("Redis unreachable: {}", e),
+                details: None,
+                last_check: Utc::now(),
+                response_time_ms: Some(start.elapsed().as_millis() as u64),
+            };
+        }
+    };
+
+    let config = JobHealthConfig::default();
+    let mut evaluator = QueueHealthEvaluator::new(config.clone());
+    let mut queues = Vec::with_capacity(config.queues.len());
+    for name in &config.queues {
+        let queue = RedisJobQueue::new(connection.clone(), name.clone());
+        let health = match (queue.depth().await, queue.heartbeats().await) {
+            (Ok(depth), Ok(heartbeats)) => evaluator.evaluate(name, Utc::now(), &depth, &heartbeats),
+            (Err(e), _) | (_, Err(e)) => QueueHealth::unavailable(name, Utc::now(), e.to_string()),
+        };
+        queues.push(health);
+    }
+
+    let unhealthy: Vec<&str> = queues.iter().filter(|q| !q.healthy).map(|q| q.queue.as_str()).collect();
+    let (status, message) = if unhealthy.is_empty() {
+        (HealthStatus::Healthy, format!("{} queue(s) consumed", queues.len()))
+    } else {
+        (HealthStatus::Warning, format!("No live consumer: {}", unhealthy.join(", ")))
+    };
+
+    ComponentStatus {
+        status,
+        message,
+        details: Some(serde_json::json!({ "queues": queues })),
+        last_check: Utc::now(),
+        response_time_ms: Some(start.elapsed().as_millis() as u64),
+    }
+}
+
 async fn check_docker() -> ComponentStatus {
     let start = std::time::Instant::now();
 
@@ -387,12 +613,12 @@ async fn get_system_uptime() -> Result<String> {
     #[cfg(target_os = "linux")]
     {
         let uptime = std::fs::read_to_string("/proc/uptime")
-            .context("Failed to read uptime")?;
+            .io_step("/proc/uptime", "read uptime")?;
         let seconds: f64 = uptime.split_whitespace()
             .next()
-            .context("Invalid uptime format")?
+            .unwrap_or_default()
             .parse()
-            .context("Failed to parse uptime")?;
+            .map_err(|e| DeployError::internal("parse /proc/uptime", e))?;
 
         let days = (seconds / 86400.0) as u64;
         let hours = ((seconds % 86400.0) / 3600.0) as u64;
@@ -443,6 +669,7 @@ fn display_status_table(status: &SystemStatus, filter_component: Option<&str>) -
         println!("Uptime: {}", uptime);
     }
     println!("Version: {}", status.version);
+    println!("CLI Build: {} (built {})", status.build.label(), status.build.built_at);
     println!();
 
     println!("{:<15} {:<10} {:<15} {:<40}", "Component", "Status", "Response Time", "Message");
@@ -475,10 +702,39 @@ fn display_status_table(status: &SystemStatus, filter_component: Option<&str>) -
         );
     }
 
+    if let Some(jobs) = status.components.get("jobs").filter(|_| filter_component.is_none_or(|f| f == "jobs")) {
+        display_job_queues(jobs);
+    }
+
     println!();
     Ok(())
 }
 
+fn display_job_queues(jobs: &ComponentStatus) {
+    let Some(queues) = jobs.details.as_ref().and_then(|d| d.get("queues")).and_then(|q| q.as_array()) else {
+        return;
+    };
+    let seconds = |queue: &serde_json::Value, field: &str| {
+        queue.get(field).and_then(|v| v.as_i64()).map(|s| format!("{}s", s)).unwrap_or_else(|| "-".to_string())
+    };
+
+    println!("\n{}", "Job Queues".bold());
+    println!("{:<20} {:<8} {:<12} {:<16} {:<10}", "Queue", "Depth", "Oldest Job", "Last Heartbeat", "Consumers");
+    println!("{}", "-".repeat(70));
+    for queue in queues {
+        let name = queue.get("queue").and_then(|v| v.as_str()).unwrap_or("?");
+        let healthy = queue.get("healthy").and_then(|v| v.as_bool()).unwrap_or(false);
+        println!(
+            "{:<20} {:<8} {:<12} {:<16} {:<10}",
+            if healthy { name.green() } else { name.red() },
+            queue.get("depth").and_then(|v| v.as_u64()).unwrap_or(0),
+            seconds(queue, "oldest_job_age_seconds"),
+            seconds(queue, "heartbeat_age_seconds"),
+            queue.get("consumers").and_then(|v| v.as_u64()).unwrap_or(0),
+        );
+    }
+}
+
 fn mask_credentials(url: &str) -> String {
     if let Ok(parsed) = url::Url::parse(url) {
         let mut masked = parsed.clone();
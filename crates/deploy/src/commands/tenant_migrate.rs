@@ -0,0 +1,430 @@
+//! Migrating the schemas of all tenants
+//!
+//! A tenant schema is made of copies of shared tables, created from the tenant
+//! schema template when the tenant was set up; later migrations only change
+//! the shared tables in `public`. Migrating a tenant brings its schema level
+//! with them, in one transaction:
+//!
+//! - tables of the template the schema lacks are created,
+//! - columns the shared tables gained are added, with their defaults,
+//! - the template's triggers are recreated, after the columns they may name,
+//! - the template's version is recorded in `public.tenant_schema_migrations`,
+//!   where the tenant health check reads it.
+//!
+//! `tenant migrate-all` does this for every active tenant, `--concurrency` at
+//! a time. A tenant that fails is retried once before it counts as failed.
+
+use colored::*;
+use sqlx::postgres::PgPoolOptions;
+use sqlx::{Executor, PgConnection, PgPool, Row};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use uuid::Uuid;
+
+use super::db_reset::{
+    qualified, quote_ident, record_template_version, validate_identifier, BUNDLED_MIGRATIONS, TENANT_SCHEMA_TEMPLATE,
+};
+use crate::error::{DbResultExt, DeployError, Result};
+
+/// Attempts per tenant: the first run and one retry
+const MAX_ATTEMPTS: u32 = 2;
+
+/// An active tenant with its own schema
+#[derive(Debug, Clone)]
+pub struct TenantSchema {
+    pub id: Uuid,
+    pub name: String,
+    pub schema: String,
+}
+
+/// What migrating one tenant changed
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TenantMigration {
+    pub created_tables: usize,
+    pub added_columns: usize,
+    /// Template version, recorded for the tenant
+    pub version: Option<i64>,
+}
+
+/// Outcome of one tenant, retry included
+#[derive(Debug)]
+pub struct TenantOutcome {
+    pub tenant: TenantSchema,
+    pub attempts: u32,
+    /// Wall-clock time of all attempts
+    pub elapsed: Duration,
+    pub result: std::result::Result<TenantMigration, String>,
+}
+
+/// First line of the template's trigger section
+const TRIGGER_SECTION: &str = "-- LIKE does not copy triggers";
+
+/// The template with `CREATE TABLE IF NOT EXISTS`, so it only adds what a schema lacks
+pub fn idempotent_template(template: &str) -> String {
+    template.replace("CREATE TABLE {TENANT_SCHEMA}.", "CREATE TABLE IF NOT EXISTS {TENANT_SCHEMA}.")
+}
+
+/// The template's tables and its triggers; a trigger may name a column the
+/// schema only gains once its tables are migrated
+pub fn template_sections(template: &str) -> (&str, &str) {
+    template.split_at(template.find(TRIGGER_SECTION).unwrap_or(template.len()))
+}
+
+/// `ALTER TABLE` adding a column of a shared table to the tenant's copy
+pub fn add_column_sql(schema: &str, table: &str, column: &str, column_type: &str, default: Option<&str>) -> String {
+    let mut sql = format!(
+        "ALTER TABLE {} ADD COLUMN IF NOT EXISTS {} {}",
+        qualified(schema, table),
+        quote_ident(column),
+        column_type
+    );
+    if let Some(default) = default {
+        sql.push_str(" DEFAULT ");
+        sql.push_str(default);
+    }
+    sql
+}
+
+pub async fn migrate_all_tenants(database_url: &str, concurrency: usize) -> Result<()> {
+    println!("{}", "🔄 Migrating all tenant schemas...".blue().bold());
+    let concurrency = concurrency.max(1);
+
+    // One connection per running tenant, plus one for the listing
+    let pool = PgPoolOptions::new()
+        .max_connections(concurrency as u32 + 1)
+        .connect(database_url)
+        .await
+        .connecting_to(database_url, "connect to database")?;
+
+    // Tenant tables copy the shared ones, so those must be current first
+    let pending = pending_shared_migrations(&pool).await?;
+    if !pending.is_empty() {
+        return Err(DeployError::invalid_input(format!(
+            "{} shared migration(s) are pending; run `erp-deploy database migrate` first",
+            pending.len()
+        )));
+    }
+
+    let tenants = active_tenants(&pool).await?;
+    if tenants.is_empty() {
+        println!("No active tenants");
+        return Ok(());
+    }
+    println!("Tenants: {}, {} at a time", tenants.len().to_string().cyan(), concurrency.to_string().cyan());
+
+    let started = Instant::now();
+    let outcomes = run_all(tenants, concurrency, move |tenant| {
+        let pool = pool.clone();
+        async move { migrate_tenant(&pool, &tenant).await.map_err(|e| e.render(false)) }
+    })
+    .await;
+    print_summary(&outcomes, started.elapsed());
+
+    let failed: Vec<&str> = outcomes
+        .iter()
+        .filter(|outcome| outcome.result.is_err())
+        .map(|outcome| outcome.tenant.name.as_str())
+        .collect();
+    if failed.is_empty() {
+        println!("{}", "✅ All tenants migrated".green().bold());
+        return Ok(());
+    }
+    Err(DeployError::MigrationFailed {
+        tenant: None,
+        step: "migrate tenants".to_string(),
+        source: anyhow::anyhow!("{} of {} tenants failed: {}", failed.len(), outcomes.len(), failed.join(", ")).into(),
+    })
+}
+
+/// Run `migrate` for every tenant, `concurrency` at a time, retrying a failure once
+pub async fn run_all<F, Fut>(tenants: Vec<TenantSchema>, concurrency: usize, migrate: F) -> Vec<TenantOutcome>
+where
+    F: Fn(TenantSchema) -> Fut + Clone + Send + 'static,
+    Fut: Future<Output = std::result::Result<TenantMigration, String>> + Send,
+{
+    let slots = Arc::new(Semaphore::new(concurrency.max(1)));
+    let mut running = JoinSet::new();
+    for (index, tenant) in tenants.into_iter().enumerate() {
+        let slots = slots.clone();
+        let migrate = migrate.clone();
+        running.spawn(async move {
+            // The semaphore is never closed
+            let _slot = slots.acquire_owned().await.ok();
+            let started = Instant::now();
+            let mut attempts = 0;
+            let result = loop {
+                attempts += 1;
+                match migrate(tenant.clone()).await {
+                    Err(e) if attempts < MAX_ATTEMPTS => {
+                        println!("{}", format!("⚠️  {} failed, retrying: {}", tenant.name, first_line(&e)).yellow());
+                    }
+                    result => break result,
+                }
+            };
+            (index, TenantOutcome { tenant, attempts, elapsed: started.elapsed(), result })
+        });
+    }
+
+    let mut outcomes = Vec::new();
+    while let Some(joined) = running.join_next().await {
+        match joined {
+            Ok(outcome) => outcomes.push(outcome),
+            Err(e) => println!("{}", format!("⚠️  A tenant migration task died: {}", e).red()),
+        }
+    }
+    // In the order the tenants were listed, not the order they finished
+    outcomes.sort_by_key(|(index, _)| *index);
+    outcomes.into_iter().map(|(_, outcome)| outcome).collect()
+}
+
+async fn pending_shared_migrations(pool: &PgPool) -> Result<Vec<i64>> {
+    let migration_table_exists: bool =
+        sqlx::query_scalar("SELECT to_regclass('public._sqlx_migrations') IS NOT NULL")
+            .fetch_one(pool)
+            .await
+            .db_step("check migration table")?;
+    let applied: Vec<i64> = if migration_table_exists {
+        sqlx::query_scalar("SELECT version FROM public._sqlx_migrations WHERE success")
+            .fetch_all(pool)
+            .await
+            .db_step("read applied migrations")?
+    } else {
+        Vec::new()
+    };
+    Ok(BUNDLED_MIGRATIONS
+        .iter()
+        .filter(|m| !m.migration_type.is_down_migration() && !applied.contains(&m.version))
+        .map(|m| m.version)
+        .collect())
+}
+
+async fn active_tenants(pool: &PgPool) -> Result<Vec<TenantSchema>> {
+    let rows = sqlx::query(
+        "SELECT id, name, schema_name FROM public.tenants \
+         WHERE status = 'active' AND schema_name IS NOT NULL AND schema_name <> 'public' \
+         ORDER BY name",
+    )
+    .fetch_all(pool)
+    .await
+    .db_step("list active tenants")?;
+    Ok(rows
+        .into_iter()
+        .map(|row| TenantSchema {
+            id: row.get("id"),
+            name: row.get("name"),
+            schema: row.get("schema_name"),
+        })
+        .collect())
+}
+
+/// Bring one tenant's schema level with the shared tables, in one transaction
+pub async fn migrate_tenant(pool: &PgPool, tenant: &TenantSchema) -> Result<TenantMigration> {
+    validate_identifier(&tenant.schema)?;
+    let schema = Some(tenant.schema.as_str());
+    let mut tx = pool.begin().await.migration_step(schema, "begin transaction")?;
+
+    let tables_before = table_count(&mut tx, &tenant.schema).await?;
+    // Run unprepared, as the template holds several statements
+    let template = idempotent_template(TENANT_SCHEMA_TEMPLATE).replace("{TENANT_SCHEMA}", &tenant.schema);
+    let (tables, triggers) = template_sections(&template);
+    tx.execute(tables).await.migration_step(schema, "create missing tables")?;
+    let created_tables = table_count(&mut tx, &tenant.schema).await? - tables_before;
+
+    let missing = sqlx::query(
+        "SELECT tc.relname AS table_name, pa.attname AS column_name, \
+                format_type(pa.atttypid, pa.atttypmod) AS column_type, \
+                pg_get_expr(d.adbin, d.adrelid) AS column_default \
+         FROM pg_class tc \
+         JOIN pg_namespace tn ON tn.oid = tc.relnamespace AND tn.nspname = $1 \
+         JOIN pg_class pc ON pc.relname = tc.relname AND pc.relkind = 'r' \
+         JOIN pg_namespace pn ON pn.oid = pc.relnamespace AND pn.nspname = 'public' \
+         JOIN pg_attribute pa ON pa.attrelid = pc.oid AND pa.attnum > 0 AND NOT pa.attisdropped \
+              AND pa.attgenerated = '' \
+         LEFT JOIN pg_attrdef d ON d.adrelid = pc.oid AND d.adnum = pa.attnum \
+         WHERE tc.relkind = 'r' \
+           AND NOT EXISTS ( \
+               SELECT 1 FROM pg_attribute ta \
+               WHERE ta.attrelid = tc.oid AND ta.attname = pa.attname AND NOT ta.attisdropped) \
+         ORDER BY tc.relname, pa.attnum",
+    )
+    .bind(&tenant.schema)
+    .fetch_all(&mut *tx)
+    .await
+    .migration_step(schema, "compare columns with the shared tables")?;
+    for row in &missing {
+        let table: String = row.get("table_name");
+        let column: String = row.get("column_name");
+        let column_type: String = row.get("column_type");
+        let default: Option<String> = row.get("column_default");
+        let sql = add_column_sql(&tenant.schema, &table, &column, &column_type, default.as_deref());
+        sqlx::query(&sql).execute(&mut *tx).await.migration_step(schema, "add missing columns")?;
+    }
+    tx.execute(triggers).await.migration_step(schema, "recreate triggers")?;
+
+    let version = record_template_version(&mut tx, tenant.id, TENANT_SCHEMA_TEMPLATE).await?;
+
+    tx.commit().await.migration_step(schema, "commit")?;
+    Ok(TenantMigration {
+        created_tables: created_tables.max(0) as usize,
+        added_columns: missing.len(),
+        version,
+    })
+}
+
+async fn table_count(conn: &mut PgConnection, schema: &str) -> Result<i64> {
+    sqlx::query_scalar("SELECT COUNT(*) FROM pg_tables WHERE schemaname = $1")
+        .bind(schema)
+        .fetch_one(conn)
+        .await
+        .migration_step(Some(schema), "count tables")
+}
+
+fn first_line(message: &str) -> &str {
+    message.lines().next().unwrap_or_default()
+}
+
+fn print_summary(outcomes: &[TenantOutcome], elapsed: Duration) {
+    println!();
+    println!("{:<30} {:<25} {:<8} {:<9} {:>9}  Details", "Tenant", "Schema", "Result", "Attempts", "Time");
+    println!("{}", "-".repeat(105));
+    for outcome in outcomes {
+        let (result, details) = match &outcome.result {
+            Ok(migration) if migration.created_tables == 0 && migration.added_columns == 0 => {
+                ("ok".green(), "up to date".to_string())
+            }
+            Ok(migration) => (
+                "ok".green(),
+                format!("{} table(s) created, {} column(s) added", migration.created_tables, migration.added_columns),
+            ),
+            Err(e) => ("failed".red(), first_line(e).to_string()),
+        };
+        println!(
+            "{:<30} {:<25} {:<8} {:<9} {:>8.1}s  {}",
+            outcome.tenant.name.cyan(),
+            outcome.tenant.schema,
+            result,
+            outcome.attempts,
+            outcome.elapsed.as_secs_f64(),
+            details
+        );
+    }
+
+    let failed = outcomes.iter().filter(|outcome| outcome.result.is_err()).count();
+    println!(
+        "\n{} succeeded, {} failed in {:.1}s",
+        (outcomes.len() - failed).to_string().green(),
+        if failed > 0 { failed.to_string().red() } else { failed.to_string().normal() },
+        elapsed.as_secs_f64()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    fn tenants(count: usize) -> Vec<TenantSchema> {
+        (0..count)
+            .map(|i| TenantSchema {
+                id: Uuid::new_v4(),
+                name: format!("Tenant {}", i),
+                schema: format!("tenant_{}", i),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_template_only_adds_missing_tables() {
+        let template = "CREATE SCHEMA IF NOT EXISTS {TENANT_SCHEMA};\n\
+                        CREATE TABLE {TENANT_SCHEMA}.users (LIKE public.users INCLUDING ALL);";
+        assert_eq!(
+            idempotent_template(template),
+            "CREATE SCHEMA IF NOT EXISTS {TENANT_SCHEMA};\n\
+             CREATE TABLE IF NOT EXISTS {TENANT_SCHEMA}.users (LIKE public.users INCLUDING ALL);"
+        );
+        assert!(!idempotent_template(TENANT_SCHEMA_TEMPLATE).contains("CREATE TABLE {TENANT_SCHEMA}."));
+    }
+
+    #[test]
+    fn test_triggers_follow_every_table() {
+        let (tables, triggers) = template_sections(TENANT_SCHEMA_TEMPLATE);
+        assert!(triggers.starts_with(TRIGGER_SECTION));
+        assert!(triggers.contains("CREATE TRIGGER trg_products_barcode"));
+        assert!(!triggers.contains("CREATE TABLE"));
+        assert!(!tables.contains("CREATE TRIGGER"));
+    }
+
+    #[test]
+    fn test_add_column_sql_quotes_names_and_keeps_the_default() {
+        assert_eq!(
+            add_column_sql("tenant_acme", "customers", "locale", "character varying(10)", Some("'en'::character varying")),
+            "ALTER TABLE \"tenant_acme\".\"customers\" ADD COLUMN IF NOT EXISTS \"locale\" character varying(10) \
+             DEFAULT 'en'::character varying"
+        );
+        assert_eq!(
+            add_column_sql("tenant_acme", "users", "last_seen", "timestamp with time zone", None),
+            "ALTER TABLE \"tenant_acme\".\"users\" ADD COLUMN IF NOT EXISTS \"last_seen\" timestamp with time zone"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_run_all_stays_within_the_concurrency_limit() {
+        let running = Arc::new(AtomicUsize::new(0));
+        let most = Arc::new(AtomicUsize::new(0));
+        let (r, m) = (running.clone(), most.clone());
+        let outcomes = run_all(tenants(10), 3, move |_| {
+            let (running, most) = (r.clone(), m.clone());
+            async move {
+                let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                most.fetch_max(now, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                running.fetch_sub(1, Ordering::SeqCst);
+                Ok(TenantMigration::default())
+            }
+        })
+        .await;
+
+        assert_eq!(outcomes.len(), 10);
+        assert_eq!(most.load(Ordering::SeqCst), 3);
+        let names: Vec<_> = outcomes.iter().map(|o| o.tenant.name.clone()).collect();
+        assert_eq!(names, tenants(10).into_iter().map(|t| t.name).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn test_failed_tenant_is_retried_once() {
+        let calls = Arc::new(Mutex::new(HashMap::<String, u32>::new()));
+        let c = calls.clone();
+        let outcomes = run_all(tenants(3), 2, move |tenant| {
+            let calls = c.clone();
+            async move {
+                let attempt = {
+                    let mut calls = calls.lock().unwrap();
+                    let count = calls.entry(tenant.schema.clone()).or_default();
+                    *count += 1;
+                    *count
+                };
+                match tenant.schema.as_str() {
+                    // Fails once, then succeeds
+                    "tenant_1" if attempt == 1 => Err("deadlock detected".to_string()),
+                    // Fails every time
+                    "tenant_2" => Err("column \"x\" cannot be cast".to_string()),
+                    _ => Ok(TenantMigration { created_tables: 1, added_columns: 0, version: Some(49) }),
+                }
+            }
+        })
+        .await;
+
+        assert_eq!(outcomes[0].attempts, 1);
+        assert!(outcomes[0].result.is_ok());
+        assert_eq!(outcomes[1].attempts, 2);
+        assert!(outcomes[1].result.is_ok());
+        assert_eq!(outcomes[2].attempts, 2);
+        assert_eq!(outcomes[2].result.as_ref().unwrap_err(), "column \"x\" cannot be cast");
+        assert_eq!(calls.lock().unwrap()["tenant_2"], 2);
+    }
+}
@@ -0,0 +1,292 @@
+//! Configuration change journal
+//!
+//! Every `config set` (and every rollback) appends an entry to a JSON-lines
+//! journal so operators can see who changed what and restore a previous value.
+//! Values of secret keys are never written in clear text; only a SHA-256
+//! fingerprint is kept so two entries can still be compared.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use crate::error::{DeployError, IoResultExt, Result};
+
+/// Prefix used for hashed secret values in the journal
+const SECRET_HASH_PREFIX: &str = "sha256:";
+
+/// Key fragments that mark a configuration value as secret
+const SECRET_KEY_MARKERS: &[&str] = &[
+    "password",
+    "secret",
+    "token",
+    "api_key",
+    "private_key",
+    "credential",
+    "database_url",
+];
+
+/// A single journaled configuration change
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConfigChange {
+    /// Short identifier used by `config rollback`
+    pub id: String,
+    pub timestamp: DateTime<Utc>,
+    /// OS user that ran the command
+    pub user: String,
+    pub key: String,
+    /// `system` for the deploy config file, `tenant` for tenant settings
+    pub scope: String,
+    pub tenant_id: Option<String>,
+    pub old_value: Option<String>,
+    pub new_value: Option<String>,
+    /// Whether the values were stored as hashes
+    pub secret: bool,
+    /// Change this entry rolled back, if any
+    pub rollback_of: Option<String>,
+}
+
+impl ConfigChange {
+    pub fn new(
+        key: &str,
+        scope: &str,
+        tenant_id: Option<&str>,
+        old_value: Option<&str>,
+        new_value: Option<&str>,
+    ) -> Self {
+        let secret = is_secret_key(key);
+        let store = |value: Option<&str>| {
+            value.map(|v| if secret { hash_secret(v) } else { v.to_string() })
+        };
+
+        Self {
+            id: Uuid::new_v4().simple().to_string()[..12].to_string(),
+            timestamp: Utc::now(),
+            user: current_os_user(),
+            key: key.to_string(),
+            scope: scope.to_string(),
+            tenant_id: tenant_id.map(|t| t.to_string()),
+            old_value: store(old_value),
+            new_value: store(new_value),
+            secret,
+            rollback_of: None,
+        }
+    }
+
+    /// Mark this change as the rollback of another entry
+    pub fn rolling_back(mut self, change_id: &str) -> Self {
+        self.rollback_of = Some(change_id.to_string());
+        self
+    }
+
+    /// Render the change as a unified-style diff line pair
+    pub fn diff_lines(&self) -> Vec<String> {
+        let old = self.old_value.as_deref().unwrap_or("<unset>");
+        let new = self.new_value.as_deref().unwrap_or("<unset>");
+        vec![format!("- {} = {}", self.key, old), format!("+ {} = {}", self.key, new)]
+    }
+}
+
+/// Append-only journal of configuration changes
+#[derive(Debug, Clone)]
+pub struct ConfigHistory {
+    path: PathBuf,
+}
+
+impl ConfigHistory {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Journal at `$ERP_CONFIG_HISTORY`, falling back to the user config directory
+    pub fn default_location() -> Self {
+        if let Ok(path) = std::env::var("ERP_CONFIG_HISTORY") {
+            return Self::new(path);
+        }
+
+        let path = dirs::config_dir()
+            .map(|d| d.join("erp-deploy").join("config_history.jsonl"))
+            .unwrap_or_else(|| PathBuf::from("config_history.jsonl"));
+        Self::new(path)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn record(&self, change: &ConfigChange) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            if !parent.as_os_str().is_empty() {
+                std::fs::create_dir_all(parent).io_step(parent, "create config history directory")?;
+            }
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .io_step(&self.path, "open config history")?;
+        writeln!(file, "{}", serde_json::to_string(change)?).io_step(&self.path, "write config history")?;
+        Ok(())
+    }
+
+    /// All entries in chronological order
+    pub fn entries(&self) -> Result<Vec<ConfigChange>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let reader = BufReader::new(std::fs::File::open(&self.path).io_step(&self.path, "read config history")?);
+        let mut entries = Vec::new();
+        for (line_no, line) in reader.lines().enumerate() {
+            let line = line.io_step(&self.path, "read config history")?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry = serde_json::from_str(&line).map_err(|e| DeployError::Config {
+                path: Some(self.path.clone()),
+                message: format!("corrupt history entry on line {}", line_no + 1),
+                source: Some(Box::new(e)),
+            })?;
+            entries.push(entry);
+        }
+        Ok(entries)
+    }
+
+    /// Entries for a single key, or all entries when `key` is `None`
+    pub fn entries_for(&self, key: Option<&str>) -> Result<Vec<ConfigChange>> {
+        Ok(self
+            .entries()?
+            .into_iter()
+            .filter(|e| key.is_none_or(|k| e.key == k))
+            .collect())
+    }
+
+    pub fn find(&self, change_id: &str) -> Result<ConfigChange> {
+        self.entries()?
+            .into_iter()
+            .find(|e| e.id == change_id)
+            .ok_or_else(|| DeployError::invalid_input(format!("Config change not found: {}", change_id)))
+    }
+}
+
+/// Whether values for this key must be redacted in the journal
+pub fn is_secret_key(key: &str) -> bool {
+    let key = key.to_lowercase();
+    SECRET_KEY_MARKERS.iter().any(|marker| key.contains(marker))
+}
+
+fn hash_secret(value: &str) -> String {
+    let digest = Sha256::digest(value.as_bytes());
+    format!("{}{}", SECRET_HASH_PREFIX, hex::encode(digest))
+}
+
+fn current_os_user() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+/// What a rollback will do before it is applied
+#[derive(Debug, Clone, PartialEq)]
+pub struct RollbackPlan {
+    pub change_id: String,
+    pub key: String,
+    pub scope: String,
+    pub tenant_id: Option<String>,
+    /// Value the change introduced
+    pub from: Option<String>,
+    /// Value that will be restored
+    pub to: Option<String>,
+}
+
+impl RollbackPlan {
+    /// Build the plan for undoing `change`
+    pub fn for_change(change: &ConfigChange) -> Result<Self> {
+        if change.secret {
+            return Err(DeployError::invalid_input(format!(
+                "Cannot roll back '{}': secret values are only journaled as hashes. Set the value explicitly instead.",
+                change.key
+            )));
+        }
+
+        Ok(Self {
+            change_id: change.id.clone(),
+            key: change.key.clone(),
+            scope: change.scope.clone(),
+            tenant_id: change.tenant_id.clone(),
+            from: change.new_value.clone(),
+            to: change.old_value.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_history() -> (tempfile::TempDir, ConfigHistory) {
+        let dir = tempfile::tempdir().unwrap();
+        let history = ConfigHistory::new(dir.path().join("config_history.jsonl"));
+        (dir, history)
+    }
+
+    #[test]
+    fn test_record_and_filter_by_key() {
+        let (_dir, history) = temp_history();
+
+        history
+            .record(&ConfigChange::new("docker.compose_file", "system", None, Some("a.yml"), Some("b.yml")))
+            .unwrap();
+        history
+            .record(&ConfigChange::new("backup.retention_days", "system", None, Some("30"), Some("7")))
+            .unwrap();
+
+        assert_eq!(history.entries().unwrap().len(), 2);
+        let filtered = history.entries_for(Some("backup.retention_days")).unwrap();
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0].old_value.as_deref(), Some("30"));
+        assert_eq!(filtered[0].new_value.as_deref(), Some("7"));
+    }
+
+    #[test]
+    fn test_secret_values_are_hashed() {
+        let (_dir, history) = temp_history();
+        let change = ConfigChange::new("database_url", "system", None, None, Some("postgres://u:hunter2@db/erp"));
+        history.record(&change).unwrap();
+
+        let raw = std::fs::read_to_string(history.path()).unwrap();
+        assert!(!raw.contains("hunter2"));
+
+        let stored = history.find(&change.id).unwrap();
+        assert!(stored.secret);
+        assert!(stored.new_value.as_deref().unwrap().starts_with(SECRET_HASH_PREFIX));
+        assert!(stored.diff_lines().iter().all(|l| !l.contains("hunter2")));
+    }
+
+    #[test]
+    fn test_secret_changes_cannot_be_rolled_back() {
+        let change = ConfigChange::new("smtp.password", "tenant", Some("acme"), Some("old"), Some("new"));
+        assert!(RollbackPlan::for_change(&change).is_err());
+    }
+
+    #[test]
+    fn test_rollback_plan_restores_old_value() {
+        let change = ConfigChange::new("locale.currency", "tenant", Some("acme"), Some("EUR"), Some("USD"));
+        let plan = RollbackPlan::for_change(&change).unwrap();
+
+        assert_eq!(plan.tenant_id.as_deref(), Some("acme"));
+        assert_eq!(plan.from.as_deref(), Some("USD"));
+        assert_eq!(plan.to.as_deref(), Some("EUR"));
+    }
+
+    #[test]
+    fn test_is_secret_key() {
+        assert!(is_secret_key("smtp.password"));
+        assert!(is_secret_key("integrations.API_KEY"));
+        assert!(!is_secret_key("server.port"));
+    }
+}
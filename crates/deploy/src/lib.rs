@@ -4,12 +4,16 @@
 
 use clap::Subcommand;
 
+pub mod build_info;
 pub mod commands;
 pub mod config;
+pub mod error;
+pub mod network;
 pub mod utils;
 
 // Re-export commonly used types
 pub use config::Config;
+pub use error::{DeployError, Result};
 
 #[derive(Subcommand)]
 pub enum ConfigCommands {
@@ -30,6 +34,9 @@ pub enum ConfigCommands {
         scope: Option<String>,
         /// Tenant ID for tenant-specific config
         tenant: Option<String>,
+        /// Show the diff of the change and exit without writing anything
+        #[arg(long)]
+        dry_run: bool,
     },
     /// Get configuration value
     Get {
@@ -40,6 +47,21 @@ pub enum ConfigCommands {
         /// Tenant ID
         tenant: Option<String>,
     },
+    /// Show journaled configuration changes
+    History {
+        /// Only show changes to this key
+        key: Option<String>,
+        /// Show at most this many recent changes
+        limit: Option<usize>,
+    },
+    /// Restore the value replaced by a journaled change
+    Rollback {
+        /// Change ID from `config history`
+        change_id: String,
+        /// Restore the old value even if the key was changed again since
+        #[arg(long)]
+        force: bool,
+    },
     /// Validate configuration file
     Validate {
         /// Configuration file path
@@ -54,11 +76,18 @@ pub enum ConfigCommands {
         /// Output file path
         output: Option<String>,
     },
+    /// Compare the configuration file with an environment's template, secrets masked
+    Diff {
+        /// Template environment (development, staging, production)
+        environment: String,
+        /// Configuration file to compare, by default the one `--config` resolves to
+        file: Option<String>,
+        /// Output format (table, json); exits with code 2 when required keys are missing
+        #[arg(short, long, default_value = "table")]
+        format: String,
+    },
 }
 
-// Error types
-pub type Result<T> = anyhow::Result<T>;
-
 // Command enums
 #[derive(Subcommand)]
 pub enum TenantCommands {
@@ -105,6 +134,136 @@ pub enum TenantCommands {
         /// Keep database schema
         keep_schema: bool,
     },
+    /// Bring the schemas of all active tenants level with the shared tables, several at a time
+    MigrateAll {
+        /// Tenants migrated at once
+        #[arg(long, default_value_t = 4)]
+        concurrency: usize,
+    },
+    /// Import a country's public holidays into tenant business calendars
+    ImportHolidays {
+        /// ISO 3166-1 alpha-2 country code, e.g. DE
+        #[arg(long)]
+        country: String,
+        /// Calendar year
+        #[arg(long)]
+        year: i32,
+        /// Only this tenant (ID, schema or name); all active tenants by default
+        #[arg(long)]
+        tenant: Option<String>,
+    },
+    /// Create a sandbox tenant from the data of an existing one, personal data anonymized
+    Sandbox {
+        /// Tenant whose data is copied (ID, schema or name)
+        #[arg(long)]
+        from: String,
+        /// Sandbox tenant name
+        #[arg(long)]
+        name: String,
+        /// Sandbox admin email; the copied users cannot log in
+        #[arg(long)]
+        email: String,
+        /// Admin password (will prompt if not provided)
+        #[arg(long)]
+        password: Option<String>,
+    },
+    /// Make a sandbox tenant's current data the snapshot it is reset to every night
+    Snapshot {
+        /// Sandbox tenant ID, schema or name
+        tenant: String,
+    },
+    /// Replace a tenant's personal data with stable pseudonyms, in place or in a copy
+    Anonymize {
+        /// Tenant ID, schema or name
+        #[arg(long)]
+        tenant: String,
+        /// Anonymize the tenant's own data; refused in production
+        #[arg(long, conflicts_with = "target_schema")]
+        in_place: bool,
+        /// Copy the tenant's data into this new schema and anonymize the copy
+        #[arg(long)]
+        target_schema: Option<String>,
+        /// Environment of the database
+        #[arg(short, long)]
+        environment: Option<String>,
+        /// Internal staff left as they are: an email, or `*@example.com` for a domain
+        #[arg(long = "keep-user")]
+        keep_users: Vec<String>,
+        /// Free text handling: drop or redact
+        #[arg(long, default_value = "drop")]
+        free_text: String,
+        /// Additional redaction pattern (PostgreSQL regular expression)
+        #[arg(long = "redact")]
+        redactions: Vec<String>,
+        /// Domain of the pseudonymized emails
+        #[arg(long, default_value = "staging.invalid")]
+        email_domain: String,
+        /// Seed of the pseudonyms; the same salt gives the same output (random by default)
+        #[arg(long)]
+        salt: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum RedisCommands {
+    /// Report (and with --repair fix) Redis keys that disagree with Postgres or lack a TTL
+    Doctor {
+        /// Redis URL
+        #[arg(long, env = "REDIS_URL", default_value = "redis://localhost:6379")]
+        redis_url: String,
+        /// Set missing TTLs and delete sessions of deleted tenants and expired revocations
+        #[arg(long)]
+        repair: bool,
+        /// Only check these namespaces (sessions, revocations, failed_logins, counters)
+        #[arg(long = "namespace")]
+        namespaces: Vec<String>,
+        /// Output format (table, json, yaml)
+        #[arg(short, long, default_value = "table")]
+        format: String,
+        /// Keys per SCAN call and commands per repair pipeline
+        #[arg(long, default_value = "200")]
+        batch_size: usize,
+        /// Keys inspected per second at most, to spare production Redis (0 = unlimited)
+        #[arg(long, default_value = "2000")]
+        max_keys_per_sec: u32,
+        /// Longest token lifetime in seconds (jwt.refresh_token_expiry); bounds revocation TTLs
+        #[arg(long, default_value = "2592000")]
+        max_token_lifetime: i64,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum EnvCommands {
+    /// Render the .env file from the application config, keeping the manual section
+    Generate {
+        /// Environment whose config/<environment>.toml is layered over config/default.toml
+        #[arg(short, long)]
+        environment: Option<String>,
+        /// .env file to write (defaults to env.file, or .env next to the compose file)
+        #[arg(short, long)]
+        file: Option<String>,
+        /// Print the file instead of writing it
+        #[arg(long)]
+        stdout: bool,
+    },
+    /// Show key-level drift between the .env file and the configuration, secrets masked
+    Diff {
+        /// Environment whose config/<environment>.toml is layered over config/default.toml
+        #[arg(short, long)]
+        environment: Option<String>,
+        /// .env file to compare
+        #[arg(short, long)]
+        file: Option<String>,
+    },
+    /// Fail on drift or on compose variables the .env file does not set (for CI)
+    Check {
+        /// Environment whose config/<environment>.toml is layered over config/default.toml
+        #[arg(short, long)]
+        environment: Option<String>,
+        /// .env file to check
+        #[arg(short, long)]
+        file: Option<String>,
+    },
 }
 
 #[derive(Subcommand)]
@@ -118,18 +277,25 @@ pub enum DatabaseCommands {
         /// Migration target
         target: Option<String>,
     },
-    /// Create database backup
+    /// Create database backup, with a manifest next to the dump
     Backup {
         /// Backup name
         name: String,
         /// Output directory
         output: Option<String>,
+        /// Only this tenant's schema (ID, name or schema) and the shared tables it references
+        #[arg(long)]
+        tenant: Option<String>,
+        /// Compression of the dump: gzip, bzip2 or none
+        #[arg(long, default_value = "gzip")]
+        compression: String,
     },
-    /// Restore from backup
+    /// Restore from backup, once its manifest checks out against the dump and the database
     Restore {
-        /// Backup name
+        /// Dump file written by `database backup`
         backup: String,
         /// Force restore
+        #[arg(long)]
         force: bool,
     },
     /// Check database health
@@ -138,13 +304,49 @@ pub enum DatabaseCommands {
         detailed: bool,
     },
     /// Show migration status
-    Status,
-    /// Reset database
+    Status {
+        /// List the audited migration runs: who applied each migration, when and how long it took
+        #[arg(long)]
+        history: bool,
+        /// Mark migrations that ran longer than this as slow
+        #[arg(long, default_value_t = 30)]
+        slow_threshold_secs: u64,
+        /// Number of audited migrations to list
+        #[arg(long, default_value_t = 20)]
+        limit: i64,
+    },
+    /// Drop and recreate the database, or one tenant's schema, empty but migrated
     Reset {
         /// Force reset without confirmation
+        #[arg(long)]
         force: bool,
-        /// Target tenant
+        /// Only reset this tenant's schema (ID, name or schema); shared tables are left alone
+        #[arg(long)]
         tenant: Option<String>,
+        /// Keep the rows of these tables of the reset schema, e.g. `tables=users,roles`;
+        /// kept tenants get an empty schema from the tenant template
+        #[arg(long)]
+        preserve: Option<String>,
+        /// Environment of the database; production needs a second operator's approval
+        #[arg(short, long)]
+        environment: Option<String>,
+        /// Approved request from `database approve`
+        #[arg(long)]
+        approval: Option<String>,
+    },
+    /// Approve another operator's pending request for a destructive operation
+    Approve {
+        /// Request ID printed when the request was filed
+        request: String,
+    },
+    /// Report gaps per document number sequence: returned numbers awaiting reuse and lapsed blocks
+    SequenceGaps {
+        /// Only this tenant (ID, schema or name)
+        #[arg(long)]
+        tenant: Option<String>,
+        /// Blocks leased longer ago than this and never returned count as lapsed
+        #[arg(long, default_value_t = 24)]
+        stale_hours: i64,
     },
 }
 
@@ -157,7 +359,15 @@ pub enum DockerCommands {
         /// Services to start
         services: Vec<String>,
         /// Run in detached mode
+        #[arg(short, long)]
         detach: bool,
+        /// Return only once the started services are healthy, or running if
+        /// they define no healthcheck; implies --detach
+        #[arg(long = "wait-for-healthy")]
+        wait_for_healthy: bool,
+        /// Give up waiting for healthy services after this many seconds
+        #[arg(long, default_value_t = 120, value_name = "SECS", requires = "wait_for_healthy")]
+        timeout: u64,
     },
     /// Stop services
     Stop {
@@ -206,6 +416,9 @@ pub enum BackupCommands {
         exclude: Vec<String>,
         /// Compression type
         compression: String,
+        /// Upload the archive to [backup.remote] afterwards
+        #[arg(long)]
+        upload: bool,
     },
     /// List backups
     List {
@@ -213,23 +426,54 @@ pub enum BackupCommands {
         directory: Option<String>,
         /// Output format
         format: String,
+        /// Include the backups held by [backup.remote]
+        #[arg(long)]
+        remote: bool,
     },
     /// Restore backup
     Restore {
         /// Backup name
         name: String,
-        /// Backup file path
+        /// Backup file path, or remote backup name with --remote
         backup: String,
         /// Force restore
         force: bool,
         /// Components to restore
         components: Vec<String>,
+        /// Fetch and verify the backup from [backup.remote] first
+        #[arg(long)]
+        remote: bool,
+    },
+    /// Upload a backup archive to [backup.remote], resuming an interrupted upload
+    Upload {
+        /// Backup archive path
+        archive: String,
+    },
+    /// Download and verify a backup from [backup.remote]
+    Fetch {
+        /// Remote backup name
+        name: String,
+        /// Output directory
+        #[arg(long)]
+        output: Option<String>,
+    },
+    /// Back up the WAL written since a full or incremental backup
+    Incremental {
+        /// Output directory
+        output: String,
+        /// Manifest, or directory, of the backup to build on
+        #[arg(long)]
+        base_backup: String,
+        /// Only show what would be backed up
+        #[arg(long)]
+        dry_run: bool,
     },
-    /// Verify backup integrity
+    /// Verify backup integrity; for an incremental backup, its whole chain
     Verify {
         /// Backup name
         name: String,
         /// Detailed verification
+        #[arg(long)]
         detailed: bool,
     },
     /// Cleanup old backups
@@ -238,5 +482,8 @@ pub enum BackupCommands {
         keep: usize,
         /// Dry run
         dry_run: bool,
+        /// Clean up [backup.remote] by its retention_days instead
+        #[arg(long)]
+        remote: bool,
     },
 }
\ No newline at end of file
@@ -5,18 +5,24 @@
 
 use clap::{Parser, Subcommand};
 use colored::*;
+use std::path::Path;
 use std::process;
+use std::time::Duration;
 
+mod build_info;
 mod commands;
 mod config;
+mod error;
+mod network;
 mod utils;
 
 use commands::*;
-use erp_deploy::{DatabaseCommands, TenantCommands, DockerCommands, BackupCommands, ConfigCommands};
+use commands::command_lock::{self, LockOptions, LockTarget};
+use erp_deploy::{DatabaseCommands, TenantCommands, DockerCommands, BackupCommands, ConfigCommands, RedisCommands, EnvCommands};
 
 #[derive(Parser)]
 #[command(name = "erp-deploy")]
-#[command(version = "1.0.0")]
+#[command(version)]
 #[command(about = "ERP System Deployment and Management CLI")]
 #[command(long_about = "
 ERP System Deployment CLI - Complete deployment and management tool
@@ -30,12 +36,19 @@ and managing ERP system instances. It supports:
 • Docker container orchestration
 • Health monitoring and diagnostics
 • Backup and recovery operations
+• Redis consistency checks and repair
+• .env generation and drift detection
 
 Examples:
   erp-deploy install --environment production
   erp-deploy tenant create --name \"Acme Corp\" --email admin@acme.com
+  erp-deploy tenant import-holidays --country DE --year 2025
   erp-deploy database migrate --tenant acme_corp
+  erp-deploy tenant migrate-all --concurrency 8
+  erp-deploy database sequence-gaps --tenant acme_corp
   erp-deploy health check --all
+  erp-deploy redis doctor --namespace sessions --repair
+  erp-deploy env check --environment production
 ")]
 struct Cli {
     #[command(subcommand)]
@@ -56,6 +69,14 @@ struct Cli {
     /// Skip confirmation prompts
     #[arg(short, long, global = true)]
     yes: bool,
+
+    /// Wait up to this many seconds for another run of the same command to finish
+    #[arg(long, global = true, value_name = "SECS")]
+    wait: Option<u64>,
+
+    /// Take the lock over from another run of the same command (asks first)
+    #[arg(long, global = true)]
+    steal_lock: bool,
 }
 
 #[derive(Subcommand)]
@@ -94,6 +115,16 @@ enum Commands {
     #[command(about = "Database operations (migrate, backup, restore)")]
     Database(DatabaseCommands),
 
+    /// Redis maintenance commands
+    #[command(subcommand)]
+    #[command(about = "Diagnose and repair Redis state (sessions, revocations, counters)")]
+    Redis(RedisCommands),
+
+    /// Environment file commands
+    #[command(subcommand)]
+    #[command(about = "Generate the .env file from the configuration and detect drift")]
+    Env(EnvCommands),
+
     /// Docker management commands
     #[command(subcommand)]
     #[command(about = "Docker container management")]
@@ -155,6 +186,11 @@ enum Commands {
         /// Output format (table, json, yaml)
         #[arg(short, long, default_value = "table")]
         format: String,
+
+        /// Show the effective configuration, with the source of each value, and whether the API runs with it
+        /// (`--config` is the global configuration file option)
+        #[arg(long)]
+        effective_config: bool,
     },
 }
 
@@ -172,13 +208,17 @@ fn main() {
     // Initialize logging based on verbosity
     init_logging(cli.verbose);
 
-    // Load configuration
+    let verbose = cli.verbose > 0;
+
+    // Load configuration; the commands that inspect the file itself also run on a broken one
+    let inspects_config_file = matches!(
+        cli.command,
+        Commands::Config(ConfigCommands::Validate { .. } | ConfigCommands::Diff { .. })
+    );
     let config = match config::load_config(cli.config.as_deref()) {
         Ok(config) => config,
-        Err(e) => {
-            eprintln!("{} Failed to load configuration: {}", "Error:".red().bold(), e);
-            process::exit(1);
-        }
+        Err(_) if inspects_config_file => config::Config::default(),
+        Err(e) => exit_with(&e, verbose),
     };
 
     // Execute command
@@ -190,14 +230,71 @@ fn main() {
         Ok(_) => {
             println!("{}", "✅ Command completed successfully".green().bold());
         }
-        Err(e) => {
-            eprintln!("{} {}", "❌ Error:".red().bold(), e);
-            process::exit(1);
+        Err(e) => exit_with(&e, verbose),
+    }
+}
+
+/// Print the failed step, error code and hint, and exit with the category's exit code
+fn exit_with(error: &error::DeployError, verbose: bool) -> ! {
+    eprintln!("{} {}", "❌".red().bold(), error.render(verbose));
+    process::exit(error.exit_code());
+}
+
+/// The lock a command takes against concurrent runs of itself, and whether
+/// it works on the database (an advisory lock) rather than files (a lock file)
+fn lock_target(command: &Commands) -> Option<(LockTarget, bool)> {
+    let (name, scope, database) = match command {
+        Commands::Database(DatabaseCommands::Migrate { tenant, .. }) => {
+            ("database migrate", tenant.clone().unwrap_or_else(|| "all".to_string()), true)
+        }
+        Commands::Database(DatabaseCommands::Reset { tenant, .. }) => {
+            ("database reset", tenant.clone().unwrap_or_else(|| "all".to_string()), true)
         }
+        Commands::Database(DatabaseCommands::Restore { .. }) => ("database restore", "default".to_string(), true),
+        Commands::Database(DatabaseCommands::Backup { tenant, .. }) => {
+            ("database backup", tenant.clone().unwrap_or_else(|| "all".to_string()), true)
+        }
+        Commands::Tenant(TenantCommands::Create { name, .. }) => ("tenant create", utils::to_schema_name(name), true),
+        Commands::Tenant(TenantCommands::Delete { tenant, .. }) => ("tenant delete", tenant.clone(), true),
+        Commands::Tenant(TenantCommands::MigrateAll { .. }) => ("tenant migrate-all", "all".to_string(), true),
+        Commands::Backup(BackupCommands::Create { .. }) => ("backup create", "all".to_string(), false),
+        Commands::Backup(BackupCommands::Restore { .. }) => ("backup restore", "all".to_string(), false),
+        Commands::Backup(BackupCommands::Incremental { .. }) => ("backup incremental", "all".to_string(), false),
+        Commands::Docker(DockerCommands::Update { .. }) => ("docker update", "all".to_string(), false),
+        _ => return None,
+    };
+    Some((LockTarget::new(name, scope), database))
+}
+
+/// Run the command under its lock, which is released however the run ends,
+/// Ctrl+C included
+async fn execute_command(cli: Cli, config: config::Config) -> error::Result<()> {
+    let Some((target, database)) = lock_target(&cli.command) else {
+        return run_command(cli, config).await;
+    };
+
+    let database_url = database
+        .then(|| cli.database_url.clone().or_else(|| config.database_url.clone()))
+        .flatten();
+    let options = LockOptions {
+        wait: cli.wait.map(Duration::from_secs),
+        steal: cli.steal_lock,
+        assume_yes: cli.yes,
+    };
+    let lock = command_lock::acquire(&target, database_url.as_deref(), Path::new(&config.install_dir), options).await?;
+
+    let result = tokio::select! {
+        result = run_command(cli, config) => result,
+        _ = tokio::signal::ctrl_c() => Err(error::DeployError::Interrupted { command: target.command.clone() }),
+    };
+
+    if let Err(e) = lock.release().await {
+        tracing::warn!("Failed to release the lock of erp-deploy {}: {}", target.command, e);
     }
+    result
 }
 
-async fn execute_command(cli: Cli, config: config::Config) -> anyhow::Result<()> {
+async fn run_command(cli: Cli, config: config::Config) -> error::Result<()> {
     match cli.command {
         Commands::Install {
             environment,
@@ -223,8 +320,16 @@ async fn execute_command(cli: Cli, config: config::Config) -> anyhow::Result<()>
             database::execute_database_command(cmd, &config, cli.database_url.as_deref()).await
         }
 
+        Commands::Redis(cmd) => {
+            redis_doctor::execute_redis_command(cmd, &config, cli.database_url.as_deref(), cli.yes).await
+        }
+
+        Commands::Env(cmd) => {
+            env_file::execute_env_command(cmd, &config).await
+        }
+
         Commands::Docker(cmd) => {
-            docker::execute_docker_command(cmd).await
+            docker::execute_docker_command(cmd, &config).await
         }
 
         Commands::Health { all, component, format } => {
@@ -232,7 +337,12 @@ async fn execute_command(cli: Cli, config: config::Config) -> anyhow::Result<()>
         }
 
         Commands::Config(cmd) => {
-            config::execute_config_command(cmd, &config).await
+            let ctx = config::ConfigContext {
+                config_path: cli.config.as_deref(),
+                database_url: cli.database_url.as_deref(),
+                assume_yes: cli.yes,
+            };
+            config::execute_config_command(cmd, &config, ctx).await
         }
 
         Commands::Backup(cmd) => {
@@ -243,8 +353,12 @@ async fn execute_command(cli: Cli, config: config::Config) -> anyhow::Result<()>
             logs::execute(component.as_deref(), follow, lines, since.as_deref()).await
         }
 
-        Commands::Status { detailed, format } => {
-            status::execute(detailed, format, None).await
+        Commands::Status { format, effective_config: true, .. } => {
+            status::show_effective_config(format, &config).await
+        }
+
+        Commands::Status { detailed, format, effective_config: false } => {
+            status::execute(detailed, format, None, &config).await
         }
     }
 }
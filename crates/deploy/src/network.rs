@@ -0,0 +1,119 @@
+//! HTTP client of the deployment CLI
+//!
+//! `health`, `status` and the remote backup commands go through the proxy and
+//! CA bundle of `[proxy]` in deploy.toml, the same keys the server reads from
+//! `[outbound.proxy]` (see [`erp_core::outbound::proxy`]). Proxy URLs may be
+//! given as `env:VARIABLE` or `file:/path` so the login stays out of the file.
+
+use erp_core::config::OutboundProxyConfig;
+use erp_core::outbound::{transport_failure, TransportFailure};
+
+use crate::config::resolve_secret;
+use crate::error::{DeployError, Result};
+
+/// Client with the proxies and CA bundle of `[proxy]`
+pub fn http_client(proxy: &OutboundProxyConfig) -> Result<reqwest::Client> {
+    let proxy = resolve_proxy(proxy)?;
+    proxy
+        .validate()
+        .map_err(|e| DeployError::config(None, format!("invalid [proxy] settings: {}", e)))?;
+    proxy
+        .apply(reqwest::Client::builder())
+        .map_err(|e| DeployError::config(None, e.to_string()))?
+        .build()
+        .map_err(|e| DeployError::internal("build HTTP client", e))
+}
+
+/// `[proxy]` with the secret references of the proxy URLs resolved
+fn resolve_proxy(proxy: &OutboundProxyConfig) -> Result<OutboundProxyConfig> {
+    let resolve = |key: &str, url: &Option<String>| url.as_deref().map(|url| resolve_secret(key, url)).transpose();
+    Ok(OutboundProxyConfig {
+        http_proxy: resolve("proxy.http_proxy", &proxy.http_proxy)?,
+        https_proxy: resolve("proxy.https_proxy", &proxy.https_proxy)?,
+        ..proxy.clone()
+    })
+}
+
+/// The refused proxy login or unverifiable certificate behind a failed
+/// request, as its own error; `None` for any other failure
+pub fn transport_error(error: &reqwest::Error, proxy: &OutboundProxyConfig, step: &str) -> Option<DeployError> {
+    let url = error.url()?;
+    match transport_failure(error)? {
+        TransportFailure::ProxyAuth => Some(DeployError::ProxyAuth {
+            proxy: resolve_proxy(proxy)
+                .ok()?
+                .proxy_for_url(url)
+                .unwrap_or_else(|| "the proxy".to_string()),
+            step: step.to_string(),
+        }),
+        TransportFailure::TlsTrust => Some(DeployError::TlsTrust {
+            host: url.host_str().unwrap_or_default().to_string(),
+            trust: proxy.trust(),
+            ca_bundle: proxy.ca_bundle.clone(),
+            step: step.to_string(),
+        }),
+    }
+}
+
+/// A proxy's 407 answer to a plain-HTTP request, which arrives as a response
+pub fn proxy_refused(response: &reqwest::Response, proxy: &OutboundProxyConfig, step: &str) -> Option<DeployError> {
+    if response.status() != reqwest::StatusCode::PROXY_AUTHENTICATION_REQUIRED {
+        return None;
+    }
+    let proxy = resolve_proxy(proxy).ok()?.proxy_for_url(response.url())?;
+    Some(DeployError::ProxyAuth {
+        proxy,
+        step: step.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn test_refused_proxy_login_is_its_own_error() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy_url = format!("http://deploy:wrong@{}", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut request = [0; 4096];
+                let _ = socket.read(&mut request).await;
+                let _ = socket
+                    .write_all(b"HTTP/1.1 407 Proxy Authentication Required\r\ncontent-length: 0\r\n\r\n")
+                    .await;
+            }
+        });
+        let proxy = OutboundProxyConfig {
+            http_proxy: Some(proxy_url.clone()),
+            https_proxy: Some(proxy_url),
+            ..Default::default()
+        };
+        let client = http_client(&proxy).unwrap();
+
+        let error = client.get("https://backups.example.com/").send().await.unwrap_err();
+        let error = transport_error(&error, &proxy, "upload backup chunk").unwrap();
+        assert_eq!(error.code(), "PROXY_AUTH");
+        let report = error.render(false);
+        assert!(report.starts_with("upload backup chunk failed [PROXY_AUTH]: proxy auth failed at http://127.0.0.1:"), "{}", report);
+        assert!(!report.contains("wrong"));
+
+        let response = client.get("http://api.example.com/health").send().await.unwrap();
+        let error = proxy_refused(&response, &proxy, "check API health").unwrap();
+        assert_eq!(error.exit_code(), crate::error::exit_codes::PROXY_AUTH);
+        server.abort();
+    }
+
+    #[test]
+    fn test_unreadable_ca_bundle_is_a_config_error() {
+        let error = http_client(&OutboundProxyConfig {
+            ca_bundle: Some("/nonexistent/ca.pem".to_string()),
+            ..Default::default()
+        })
+        .unwrap_err();
+        assert_eq!(error.code(), "CONFIG");
+        assert!(error.to_string().contains("cannot read CA bundle /nonexistent/ca.pem"), "{}", error);
+    }
+}
@@ -1,6 +1,6 @@
 //! Utility functions for the deployment CLI
 
-use anyhow::Result;
+use crate::error::Result;
 use std::process::Command;
 
 /// Check if a command exists in the system PATH
@@ -0,0 +1,232 @@
+//! Caller authentication from gRPC metadata.
+//!
+//! Mirrors `erp_auth::auth_middleware`. The bearer token must verify and
+//! must not be revoked. It must not have been replaced by a sliding session
+//! refresh more than its grace period ago, nor predate the user's last
+//! password change. The tenant named in `x-tenant-id` must be the token's
+//! tenant. Each call then checks a single permission. The tenant's schema
+//! comes from the [`TenantContextResolver`].
+
+use erp_core::{
+    security::{is_past_grace, is_token_superseded, token_cutoff_keys, token_revoked_after_key, JwtService},
+    ErrorCode, TenantContext, TenantContextResolver,
+};
+use redis::aio::ConnectionManager;
+use std::sync::Arc;
+use tonic::{metadata::MetadataMap, Status};
+use tracing::{error, warn};
+use uuid::Uuid;
+
+/// Permission needed by the read RPCs
+pub const INVENTORY_READ: &str = "inventory:read";
+/// Permission needed by movements and reservations
+pub const INVENTORY_WRITE: &str = "inventory:write";
+
+/// Metadata key naming the tenant a call acts on
+pub const TENANT_METADATA_KEY: &str = "x-tenant-id";
+
+/// An authenticated caller
+#[derive(Debug, Clone)]
+pub struct Caller {
+    pub user_id: Uuid,
+    pub tenant: TenantContext,
+    pub permissions: Vec<String>,
+}
+
+#[derive(Clone)]
+pub struct Authenticator {
+    jwt_service: Arc<JwtService>,
+    tenants: Arc<TenantContextResolver>,
+    /// Revocation list; without it revoked tokens stay valid until they expire
+    redis: Option<ConnectionManager>,
+}
+
+impl Authenticator {
+    pub fn new(jwt_service: Arc<JwtService>, tenants: Arc<TenantContextResolver>) -> Self {
+        Self {
+            jwt_service,
+            tenants,
+            redis: None,
+        }
+    }
+
+    /// Reject tokens revoked through the auth service
+    pub fn with_revocation_check(mut self, redis: ConnectionManager) -> Self {
+        self.redis = Some(redis);
+        self
+    }
+
+    /// Authenticate the caller and require `permission`
+    pub async fn authorize(&self, metadata: &MetadataMap, permission: &str) -> Result<Caller, Status> {
+        let token = bearer_token(metadata).ok_or_else(|| Status::unauthenticated("Missing authorization token"))?;
+
+        let claims = self.jwt_service.verify_access_token(token).map_err(|e| {
+            warn!("gRPC token verification failed: {}", e);
+            Status::unauthenticated("Invalid or expired token")
+        })?;
+
+        if self.is_revoked(&claims.jti).await || self.is_superseded(&claims.tenant_id, &claims.sub, claims.iat).await {
+            return Err(Status::unauthenticated("Token has been revoked"));
+        }
+
+        let token_tenant = Uuid::parse_str(&claims.tenant_id).map_err(|_| {
+            error!("Invalid tenant ID in token: {}", claims.tenant_id);
+            Status::unauthenticated("Invalid token claims")
+        })?;
+        let user_id = Uuid::parse_str(&claims.sub).map_err(|_| {
+            error!("Invalid user ID in token: {}", claims.sub);
+            Status::unauthenticated("Invalid token claims")
+        })?;
+
+        let tenant_id = requested_tenant(metadata)?;
+        if tenant_id != token_tenant {
+            warn!("Caller {} of tenant {} requested tenant {}", user_id, token_tenant, tenant_id);
+            return Err(Status::permission_denied("Token is not valid for this tenant"));
+        }
+
+        if !claims.permissions.iter().any(|p| p == permission) {
+            warn!("Caller {} lacks required permission: {}", user_id, permission);
+            return Err(Status::permission_denied(format!("Missing required permission: {}", permission)));
+        }
+
+        let tenant = self.tenants.resolve(tenant_id).await.map_err(|e| match e.code {
+            ErrorCode::ResourceNotFound => Status::not_found(format!("Tenant {} not found", tenant_id)),
+            _ => {
+                error!("Failed to resolve tenant {}: {}", tenant_id, e);
+                Status::unavailable("Tenant lookup failed")
+            }
+        })?;
+
+        Ok(Caller {
+            user_id,
+            tenant,
+            permissions: claims.permissions,
+        })
+    }
+
+    async fn is_revoked(&self, jti: &str) -> bool {
+        let Some(redis) = &self.redis else {
+            return false;
+        };
+
+        let mut conn = redis.clone();
+        let revoked = match redis::AsyncCommands::exists::<_, bool>(&mut conn, format!("revoked_token:{}", jti)).await {
+            Ok(exists) => exists,
+            Err(e) => {
+                error!("Failed to check token revocation: {}", e);
+                false // Allow on Redis error to prevent complete lockout
+            }
+        };
+        if revoked {
+            return true;
+        }
+
+        match redis::AsyncCommands::get::<_, Option<i64>>(&mut conn, token_revoked_after_key(jti)).await {
+            Ok(revoked_after) => is_past_grace(chrono::Utc::now().timestamp(), revoked_after),
+            Err(e) => {
+                error!("Failed to check token replacement: {}", e);
+                false
+            }
+        }
+    }
+
+    async fn is_superseded(&self, tenant_id: &str, user_id: &str, issued_at: i64) -> bool {
+        let Some(redis) = &self.redis else {
+            return false;
+        };
+
+        let mut conn = redis.clone();
+        match redis::AsyncCommands::mget::<_, Vec<Option<i64>>>(&mut conn, &token_cutoff_keys(tenant_id, user_id)).await {
+            Ok(cutoffs) => is_token_superseded(issued_at, cutoffs.into_iter().flatten().max()),
+            Err(e) => {
+                error!("Failed to check token cutoff: {}", e);
+                false // Allow on Redis error to prevent complete lockout
+            }
+        }
+    }
+}
+
+fn bearer_token(metadata: &MetadataMap) -> Option<&str> {
+    metadata
+        .get("authorization")?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+}
+
+fn requested_tenant(metadata: &MetadataMap) -> Result<Uuid, Status> {
+    let value = metadata
+        .get(TENANT_METADATA_KEY)
+        .ok_or_else(|| Status::invalid_argument("Missing x-tenant-id metadata"))?;
+
+    value
+        .to_str()
+        .ok()
+        .and_then(|v| Uuid::parse_str(v.trim()).ok())
+        .ok_or_else(|| Status::invalid_argument("x-tenant-id must be a UUID"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use erp_core::config::JwtConfig;
+    use tonic::Code;
+
+    fn jwt() -> Arc<JwtService> {
+        Arc::new(
+            JwtService::new(&JwtConfig {
+                secret: "grpc-auth-test-secret-with-enough-length".to_string(),
+                access_token_expiry: 900,
+                refresh_token_expiry: 3600,
+            })
+            .unwrap(),
+        )
+    }
+
+    fn metadata(token: &str, tenant: &str) -> MetadataMap {
+        let mut metadata = MetadataMap::new();
+        metadata.insert("authorization", format!("Bearer {}", token).parse().unwrap());
+        metadata.insert(TENANT_METADATA_KEY, tenant.parse().unwrap());
+        metadata
+    }
+
+    #[tokio::test]
+    async fn test_tenant_and_permission_checks() {
+        let jwt = jwt();
+        let (tenant, user) = (Uuid::new_v4(), Uuid::new_v4());
+        let pool = sqlx::postgres::PgPoolOptions::new()
+            .connect_lazy("postgres://postgres@127.0.0.1:1/none")
+            .unwrap();
+        let tenants = Arc::new(TenantContextResolver::new(pool));
+        tenants.prime(&TenantContext {
+            tenant_id: erp_core::TenantId(tenant),
+            schema_name: "acme_corp_7f3a".to_string(),
+        });
+        let authenticator = Authenticator::new(jwt.clone(), tenants);
+        let token = jwt
+            .generate_token_pair(&user.to_string(), &tenant.to_string(), vec![], vec![INVENTORY_READ.to_string()], None)
+            .unwrap()
+            .access_token;
+
+        let caller = authenticator.authorize(&metadata(&token, &tenant.to_string()), INVENTORY_READ).await.unwrap();
+        assert_eq!((caller.user_id, caller.tenant.tenant_id.0), (user, tenant));
+        assert_eq!(caller.tenant.schema_name, "acme_corp_7f3a");
+
+        let other_tenant = authenticator
+            .authorize(&metadata(&token, &Uuid::new_v4().to_string()), INVENTORY_READ)
+            .await
+            .unwrap_err();
+        assert_eq!(other_tenant.code(), Code::PermissionDenied);
+
+        let write = authenticator.authorize(&metadata(&token, &tenant.to_string()), INVENTORY_WRITE).await.unwrap_err();
+        assert_eq!(write.code(), Code::PermissionDenied);
+
+        let forged = authenticator.authorize(&metadata("not-a-jwt", &tenant.to_string()), INVENTORY_READ).await.unwrap_err();
+        assert_eq!(forged.code(), Code::Unauthenticated);
+
+        let missing = authenticator.authorize(&MetadataMap::new(), INVENTORY_READ).await.unwrap_err();
+        assert_eq!(missing.code(), Code::Unauthenticated);
+    }
+}
@@ -0,0 +1,86 @@
+//! The inventory operations behind the RPCs.
+//!
+//! [`PostgresInventoryBackend`] runs each call through a
+//! [`DefaultInventoryService`] on the tenant's own pool, acting as the
+//! caller, so validation and data access are shared with the REST API.
+
+use async_trait::async_trait;
+use crate::auth::Caller;
+use erp_core::actor::ActorContext;
+use erp_core::{DatabasePool, LatencyBudget};
+use erp_master_data::inventory::{
+    CreateReservationRequest, DefaultInventoryService, InventoryReservation, InventoryService,
+    PostgresInventoryRepository, StockAvailability, UpdateInventoryRequest,
+};
+use erp_master_data::{LocationInventory, Result};
+use std::sync::Arc;
+use uuid::Uuid;
+
+#[async_trait]
+pub trait InventoryBackend: Send + Sync {
+    async fn location_inventory(&self, caller: &Caller, product_id: Uuid, location_id: Uuid) -> Result<LocationInventory>;
+
+    /// Stock for each known pair; pairs without inventory are omitted
+    async fn stock_availability(&self, caller: &Caller, pairs: &[(Uuid, Uuid)]) -> Result<Vec<StockAvailability>>;
+
+    async fn record_movement(&self, caller: &Caller, request: UpdateInventoryRequest) -> Result<LocationInventory>;
+
+    async fn create_reservation(&self, caller: &Caller, request: CreateReservationRequest) -> Result<InventoryReservation>;
+}
+
+pub struct PostgresInventoryBackend {
+    db: DatabasePool,
+    quarantine_location_types: Vec<String>,
+    latency: LatencyBudget,
+}
+
+impl PostgresInventoryBackend {
+    pub fn new(db: DatabasePool) -> Self {
+        Self {
+            db,
+            quarantine_location_types: vec!["quarantine".to_string()],
+            latency: LatencyBudget::default(),
+        }
+    }
+
+    /// Location types whose stock is reported as not sellable
+    pub fn with_quarantine_location_types(mut self, location_types: Vec<String>) -> Self {
+        self.quarantine_location_types = location_types;
+        self
+    }
+
+    /// Time inventory service methods against these budgets
+    pub fn with_latency_budget(mut self, latency: LatencyBudget) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    async fn service(&self, caller: &Caller) -> Result<DefaultInventoryService> {
+        let tenant_pool = self.db.get_tenant_pool(&caller.tenant).await?;
+        let repository = PostgresInventoryRepository::new(tenant_pool.pool)
+            .with_quarantine_location_types(self.quarantine_location_types.clone());
+        Ok(DefaultInventoryService::new(Arc::new(repository))
+            .with_tenant(caller.tenant.tenant_id.0)
+            .with_actor(ActorContext::user(caller.user_id))
+            .with_latency_budget(self.latency.clone()))
+    }
+}
+
+#[async_trait]
+impl InventoryBackend for PostgresInventoryBackend {
+    async fn location_inventory(&self, caller: &Caller, product_id: Uuid, location_id: Uuid) -> Result<LocationInventory> {
+        self.service(caller).await?.get_location_inventory(product_id, location_id).await
+    }
+
+    async fn stock_availability(&self, caller: &Caller, pairs: &[(Uuid, Uuid)]) -> Result<Vec<StockAvailability>> {
+        self.service(caller).await?.get_stock_availability(pairs).await
+    }
+
+    async fn record_movement(&self, caller: &Caller, request: UpdateInventoryRequest) -> Result<LocationInventory> {
+        self.service(caller).await?.update_inventory_levels(request).await
+    }
+
+    async fn create_reservation(&self, caller: &Caller, request: CreateReservationRequest) -> Result<InventoryReservation> {
+        self.service(caller).await?.create_reservation(request).await
+    }
+}
@@ -0,0 +1,32 @@
+//! # Internal gRPC API
+//!
+//! A narrow, binary-encoded inventory surface for internal services that
+//! call it at high volume, such as the picking optimizer. It serves the
+//! `erp.inventory.v1.InventoryService` defined in `proto/` on its own port
+//! (see [`erp_core::GrpcConfig`]) and maps every call onto the same
+//! [`erp_master_data::InventoryService`] the REST API uses.
+//!
+//! Callers authenticate with a service-account access token and name the
+//! tenant in metadata; [`auth::Authenticator`] applies the same tenant and
+//! permission checks as the HTTP middleware.
+
+// Handlers return `tonic::Status`, which is large by design
+#![allow(clippy::result_large_err)]
+
+pub mod auth;
+pub mod backend;
+pub mod server;
+pub mod service;
+
+/// Code generated from `proto/erp/inventory/v1/inventory.proto`
+pub mod proto {
+    tonic::include_proto!("erp.inventory.v1");
+
+    /// Encoded descriptors served by the reflection service
+    pub const FILE_DESCRIPTOR_SET: &[u8] = tonic::include_file_descriptor_set!("inventory_descriptor");
+}
+
+pub use auth::{Authenticator, Caller, INVENTORY_READ, INVENTORY_WRITE};
+pub use backend::{InventoryBackend, PostgresInventoryBackend};
+pub use server::{router, serve, GrpcServerError};
+pub use service::InventoryGrpcService;
@@ -0,0 +1,52 @@
+//! Internal gRPC inventory server.
+//!
+//! Shares configuration, database and token secret with `erp-server` and
+//! listens on `[grpc] port`. Exits immediately unless `[grpc] enabled`.
+
+use erp_core::{security::JwtService, Config, DatabasePool, LatencyBudget, TenantContextResolver};
+use erp_grpc::{serve, Authenticator, InventoryGrpcService, PostgresInventoryBackend};
+use redis::aio::ConnectionManager;
+use std::sync::Arc;
+use tracing::info;
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| "erp_grpc=debug,erp_master_data=info,erp_core=info".into()),
+        )
+        .with(tracing_subscriber::fmt::layer())
+        .init();
+
+    let config = Config::load()?;
+    if !config.grpc.enabled {
+        info!("gRPC server is disabled ([grpc] enabled = false)");
+        return Ok(());
+    }
+
+    let db = DatabasePool::new(config.database.clone()).await?;
+    let redis = ConnectionManager::new(redis::Client::open(config.redis.url.as_str())?).await?;
+    info!("Database and Redis connections established");
+
+    let tenants = Arc::new(TenantContextResolver::new(db.main_pool.clone()).with_redis(redis.clone()));
+    let authenticator = Authenticator::new(Arc::new(JwtService::new(&config.jwt)?), tenants).with_revocation_check(redis);
+    let service = InventoryGrpcService::new(
+        authenticator,
+        Arc::new(
+            PostgresInventoryBackend::new(db)
+                .with_quarantine_location_types(config.returns.quarantine_location_types.clone())
+                .with_latency_budget(LatencyBudget::new(config.latency_budgets.clone())),
+        ),
+        config.grpc.max_batch_size,
+    );
+
+    serve(&config.grpc, service, async {
+        let _ = tokio::signal::ctrl_c().await;
+        info!("Shutting down gRPC server");
+    })
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,80 @@
+//! Server setup: optional TLS and reflection on the configured port.
+
+use crate::proto::{self, inventory_service_server::InventoryServiceServer};
+use crate::service::InventoryGrpcService;
+use erp_core::GrpcConfig;
+use std::future::Future;
+use std::net::SocketAddr;
+use tonic::transport::{server::Router, Identity, Server, ServerTlsConfig};
+use tracing::info;
+
+#[derive(Debug, thiserror::Error)]
+pub enum GrpcServerError {
+    #[error("Invalid gRPC configuration: {0}")]
+    Config(String),
+
+    #[error("Failed to read {path}: {source}")]
+    Io { path: String, source: std::io::Error },
+
+    #[error("gRPC transport error: {0}")]
+    Transport(#[from] tonic::transport::Error),
+
+    #[error("gRPC reflection error: {0}")]
+    Reflection(#[from] tonic_reflection::server::Error),
+}
+
+/// Build the server with the inventory service, plus TLS and reflection when configured
+pub fn router(config: &GrpcConfig, service: InventoryGrpcService) -> Result<Router, GrpcServerError> {
+    let mut builder = Server::builder();
+
+    match (&config.tls_cert_path, &config.tls_key_path) {
+        (Some(cert_path), Some(key_path)) => {
+            let identity = Identity::from_pem(read(cert_path)?, read(key_path)?);
+            builder = builder.tls_config(ServerTlsConfig::new().identity(identity))?;
+        }
+        (None, None) => {}
+        _ => {
+            return Err(GrpcServerError::Config(
+                "tls_cert_path and tls_key_path must be set together".to_string(),
+            ))
+        }
+    }
+
+    let reflection = if config.reflection {
+        Some(
+            tonic_reflection::server::Builder::configure()
+                .register_encoded_file_descriptor_set(proto::FILE_DESCRIPTOR_SET)
+                .build_v1()?,
+        )
+    } else {
+        None
+    };
+
+    Ok(builder
+        .add_service(InventoryServiceServer::new(service))
+        .add_optional_service(reflection))
+}
+
+/// Serve on `host:port` until `shutdown` resolves
+pub async fn serve(
+    config: &GrpcConfig,
+    service: InventoryGrpcService,
+    shutdown: impl Future<Output = ()>,
+) -> Result<(), GrpcServerError> {
+    let addr: SocketAddr = format!("{}:{}", config.host, config.port)
+        .parse()
+        .map_err(|e| GrpcServerError::Config(format!("invalid listen address: {}", e)))?;
+
+    info!(
+        "gRPC inventory server listening on {} (tls: {}, reflection: {})",
+        addr,
+        config.tls_cert_path.is_some(),
+        config.reflection
+    );
+    router(config, service)?.serve_with_shutdown(addr, shutdown).await?;
+    Ok(())
+}
+
+fn read(path: &str) -> Result<Vec<u8>, GrpcServerError> {
+    std::fs::read(path).map_err(|source| GrpcServerError::Io { path: path.to_string(), source })
+}
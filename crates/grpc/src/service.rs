@@ -0,0 +1,285 @@
+//! `erp.inventory.v1.InventoryService` implementation.
+
+use crate::auth::{Authenticator, INVENTORY_READ, INVENTORY_WRITE};
+use crate::backend::InventoryBackend;
+use crate::proto;
+use crate::proto::inventory_service_server::InventoryService as InventoryRpc;
+use chrono::{DateTime, Utc};
+use erp_master_data::inventory::{CreateReservationRequest, InventoryReservation, MovementType, ReservationPriority, UpdateInventoryRequest};
+use erp_master_data::{LocationInventory, MasterDataError, ReservationType};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tonic::{Request, Response, Status};
+use tracing::error;
+use uuid::Uuid;
+
+#[derive(Clone)]
+pub struct InventoryGrpcService {
+    authenticator: Authenticator,
+    backend: Arc<dyn InventoryBackend>,
+    max_batch_size: usize,
+}
+
+impl InventoryGrpcService {
+    pub fn new(authenticator: Authenticator, backend: Arc<dyn InventoryBackend>, max_batch_size: usize) -> Self {
+        Self { authenticator, backend, max_batch_size }
+    }
+}
+
+#[tonic::async_trait]
+impl InventoryRpc for InventoryGrpcService {
+    async fn get_location_inventory(
+        &self,
+        request: Request<proto::GetLocationInventoryRequest>,
+    ) -> Result<Response<proto::LocationInventory>, Status> {
+        let caller = self.authenticator.authorize(request.metadata(), INVENTORY_READ).await?;
+        let request = request.into_inner();
+        let product_id = parse_id("product_id", &request.product_id)?;
+        let location_id = parse_id("location_id", &request.location_id)?;
+
+        let inventory = self
+            .backend
+            .location_inventory(&caller, product_id, location_id)
+            .await
+            .map_err(to_status)?;
+
+        Ok(Response::new(inventory_message(&inventory)))
+    }
+
+    async fn batch_get_availability(
+        &self,
+        request: Request<proto::BatchGetAvailabilityRequest>,
+    ) -> Result<Response<proto::BatchGetAvailabilityResponse>, Status> {
+        let caller = self.authenticator.authorize(request.metadata(), INVENTORY_READ).await?;
+        let request = request.into_inner();
+        if request.items.len() > self.max_batch_size {
+            return Err(Status::invalid_argument(format!(
+                "At most {} items per call, got {}",
+                self.max_batch_size,
+                request.items.len()
+            )));
+        }
+
+        let pairs = request
+            .items
+            .iter()
+            .map(|item| Ok((parse_id("product_id", &item.product_id)?, parse_id("location_id", &item.location_id)?)))
+            .collect::<Result<Vec<_>, Status>>()?;
+
+        let found: HashMap<(Uuid, Uuid), _> = self
+            .backend
+            .stock_availability(&caller, &pairs)
+            .await
+            .map_err(to_status)?
+            .into_iter()
+            .map(|availability| ((availability.product_id, availability.location_id), availability))
+            .collect();
+
+        let items = pairs
+            .iter()
+            .map(|pair| match found.get(pair) {
+                Some(stock) => proto::Availability {
+                    product_id: pair.0.to_string(),
+                    location_id: pair.1.to_string(),
+                    found: true,
+                    quantity_available: stock.quantity_available,
+                    quantity_reserved: stock.quantity_reserved,
+                    quantity_on_order: stock.quantity_on_order,
+                    quantity_in_transit: stock.quantity_in_transit,
+                    available_to_promise: stock.available_to_promise(),
+                },
+                None => proto::Availability {
+                    product_id: pair.0.to_string(),
+                    location_id: pair.1.to_string(),
+                    ..Default::default()
+                },
+            })
+            .collect();
+
+        Ok(Response::new(proto::BatchGetAvailabilityResponse { items }))
+    }
+
+    async fn create_movement(
+        &self,
+        request: Request<proto::CreateMovementRequest>,
+    ) -> Result<Response<proto::LocationInventory>, Status> {
+        let caller = self.authenticator.authorize(request.metadata(), INVENTORY_WRITE).await?;
+        let request = request.into_inner();
+
+        let movement = UpdateInventoryRequest {
+            product_id: parse_id("product_id", &request.product_id)?,
+            location_id: parse_id("location_id", &request.location_id)?,
+            quantity_change: request.quantity_change,
+            movement_type: movement_type(request.movement_type)?,
+            reason: request.reason,
+            reference_document: request.reference_document,
+            batch_number: request.batch_number,
+            unit_cost: request.unit_cost,
+            effective_date: request.effective_date.map(timestamp_to_datetime).transpose()?,
+            operator_id: caller.user_id,
+        };
+
+        let inventory = self
+            .backend
+            .record_movement(&caller, movement)
+            .await
+            .map_err(to_status)?;
+
+        Ok(Response::new(inventory_message(&inventory)))
+    }
+
+    async fn create_reservation(
+        &self,
+        request: Request<proto::CreateReservationRequest>,
+    ) -> Result<Response<proto::Reservation>, Status> {
+        let caller = self.authenticator.authorize(request.metadata(), INVENTORY_WRITE).await?;
+        let request = request.into_inner();
+
+        let reserved_until = request
+            .reserved_until
+            .map(timestamp_to_datetime)
+            .transpose()?
+            .ok_or_else(|| Status::invalid_argument("reserved_until is required"))?;
+
+        let reservation = CreateReservationRequest {
+            product_id: parse_id("product_id", &request.product_id)?,
+            location_id: parse_id("location_id", &request.location_id)?,
+            quantity: request.quantity,
+            reservation_type: reservation_type(request.reservation_type)?,
+            reference_id: parse_id("reference_id", &request.reference_id)?,
+            priority: reservation_priority(request.priority),
+            reserved_until,
+            notes: request.notes,
+            created_by: caller.user_id,
+        };
+
+        let reservation = self
+            .backend
+            .create_reservation(&caller, reservation)
+            .await
+            .map_err(to_status)?;
+
+        Ok(Response::new(reservation_message(&reservation)))
+    }
+}
+
+fn parse_id(field: &str, value: &str) -> Result<Uuid, Status> {
+    Uuid::parse_str(value).map_err(|_| Status::invalid_argument(format!("{} must be a UUID", field)))
+}
+
+/// Map service errors onto gRPC codes the way the REST handlers map them onto HTTP statuses
+fn to_status(e: MasterDataError) -> Status {
+    match e {
+        MasterDataError::ValidationError { field, message } => Status::invalid_argument(format!("{}: {}", field, message)),
+        MasterDataError::NotFound
+        | MasterDataError::NotFoundError(_)
+        | MasterDataError::ProductNotFound { .. }
+        | MasterDataError::LocationNotFound { .. }
+        | MasterDataError::Database(sqlx::Error::RowNotFound) => Status::not_found("Inventory not found"),
+        MasterDataError::PermissionDenied { action } => Status::permission_denied(action),
+        e => {
+            error!("gRPC inventory call failed: {}", e);
+            Status::internal("Internal error")
+        }
+    }
+}
+
+fn movement_type(value: i32) -> Result<MovementType, Status> {
+    use proto::MovementType as Proto;
+
+    Ok(match Proto::try_from(value).unwrap_or(Proto::Unspecified) {
+        Proto::Unspecified => return Err(Status::invalid_argument("movement_type is required")),
+        Proto::Receipt => MovementType::Receipt,
+        Proto::Shipment => MovementType::Shipment,
+        Proto::Transfer => MovementType::Transfer,
+        Proto::Adjustment => MovementType::Adjustment,
+        Proto::Return => MovementType::Return,
+        Proto::Damage => MovementType::Damage,
+        Proto::Loss => MovementType::Loss,
+        Proto::Found => MovementType::Found,
+        Proto::Production => MovementType::Production,
+        Proto::Consumption => MovementType::Consumption,
+        Proto::CycleCount => MovementType::CycleCount,
+        Proto::PhysicalCount => MovementType::PhysicalCount,
+    })
+}
+
+fn reservation_type(value: i32) -> Result<ReservationType, Status> {
+    use proto::ReservationType as Proto;
+
+    Ok(match Proto::try_from(value).unwrap_or(Proto::Unspecified) {
+        Proto::Unspecified => return Err(Status::invalid_argument("reservation_type is required")),
+        Proto::SalesOrder => ReservationType::SalesOrder,
+        Proto::ProductionOrder => ReservationType::ProductionOrder,
+        Proto::Transfer => ReservationType::Transfer,
+        Proto::Quality => ReservationType::Quality,
+        Proto::Damage => ReservationType::Damage,
+        Proto::Special => ReservationType::Special,
+        Proto::Promotional => ReservationType::Promotional,
+    })
+}
+
+fn reservation_priority(value: i32) -> ReservationPriority {
+    use proto::ReservationPriority as Proto;
+
+    match Proto::try_from(value).unwrap_or(Proto::Unspecified) {
+        Proto::Low => ReservationPriority::Low,
+        Proto::Unspecified | Proto::Normal => ReservationPriority::Normal,
+        Proto::High => ReservationPriority::High,
+        Proto::Critical => ReservationPriority::Critical,
+    }
+}
+
+fn priority_message(priority: &ReservationPriority) -> proto::ReservationPriority {
+    match priority {
+        ReservationPriority::Low => proto::ReservationPriority::Low,
+        ReservationPriority::Normal => proto::ReservationPriority::Normal,
+        ReservationPriority::High => proto::ReservationPriority::High,
+        ReservationPriority::Critical => proto::ReservationPriority::Critical,
+    }
+}
+
+fn timestamp_to_datetime(timestamp: prost_types::Timestamp) -> Result<DateTime<Utc>, Status> {
+    u32::try_from(timestamp.nanos)
+        .ok()
+        .and_then(|nanos| DateTime::from_timestamp(timestamp.seconds, nanos))
+        .ok_or_else(|| Status::invalid_argument("Invalid timestamp"))
+}
+
+fn timestamp(value: DateTime<Utc>) -> prost_types::Timestamp {
+    prost_types::Timestamp {
+        seconds: value.timestamp(),
+        nanos: value.timestamp_subsec_nanos() as i32,
+    }
+}
+
+fn inventory_message(inventory: &LocationInventory) -> proto::LocationInventory {
+    proto::LocationInventory {
+        product_id: inventory.product_id.to_string(),
+        location_id: inventory.location_id.to_string(),
+        location_name: inventory.location_name.clone(),
+        quantity_available: inventory.quantity_available,
+        quantity_reserved: inventory.quantity_reserved,
+        quantity_on_order: inventory.quantity_on_order,
+        quantity_in_transit: inventory.quantity_in_transit,
+        reorder_point: inventory.reorder_point,
+        safety_stock: inventory.safety_stock,
+        min_stock_level: inventory.min_stock_level,
+        max_stock_level: inventory.max_stock_level,
+        lead_time_days: inventory.lead_time_days,
+        updated_at: Some(timestamp(inventory.updated_at)),
+    }
+}
+
+fn reservation_message(reservation: &InventoryReservation) -> proto::Reservation {
+    proto::Reservation {
+        id: reservation.id.to_string(),
+        product_id: reservation.product_id.to_string(),
+        location_id: reservation.location_id.to_string(),
+        quantity: reservation.quantity_reserved,
+        priority: priority_message(&reservation.priority) as i32,
+        reference_id: reservation.reference_id.to_string(),
+        reserved_until: reservation.reserved_until.map(timestamp),
+        created_at: Some(timestamp(reservation.created_at)),
+    }
+}
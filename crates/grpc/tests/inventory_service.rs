@@ -0,0 +1,443 @@
+//! Integration tests: a tonic client against an in-process server backed by
+//! an in-memory inventory.
+
+use async_trait::async_trait;
+use chrono::Utc;
+use erp_core::{config::JwtConfig, security::JwtService, GrpcConfig, TenantContext, TenantContextResolver, TenantId};
+use erp_grpc::proto::{self, inventory_service_client::InventoryServiceClient};
+use erp_grpc::{router, Authenticator, Caller, InventoryBackend, InventoryGrpcService, INVENTORY_READ, INVENTORY_WRITE};
+use erp_master_data::inventory::{
+    ABCClassification, CreateReservationRequest, InventoryReservation, LocationType, MovementVelocity,
+    ReservationStatus, StockAvailability, StorageRequirements, UpdateInventoryRequest,
+};
+use erp_master_data::{LocationInventory, MasterDataError, Result};
+use sqlx::postgres::PgPoolOptions;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tokio::net::TcpListener;
+use tokio_stream::wrappers::TcpListenerStream;
+use tonic::transport::Channel;
+use tonic::{Code, Request};
+use uuid::Uuid;
+
+/// Inventory keyed by tenant, product and location; counts backend calls
+#[derive(Default)]
+struct MemoryInventory {
+    items: Mutex<HashMap<(Uuid, Uuid, Uuid), LocationInventory>>,
+    calls: AtomicUsize,
+}
+
+impl MemoryInventory {
+    fn stock(&self, tenant_id: Uuid, product_id: Uuid, location_id: Uuid, quantity_available: i32) {
+        let now = Utc::now();
+        let inventory = LocationInventory {
+            id: Uuid::new_v4(),
+            product_id,
+            location_id,
+            location_name: "Main warehouse".to_string(),
+            location_type: LocationType::Warehouse,
+            quantity_available,
+            quantity_reserved: 0,
+            quantity_on_order: 5,
+            quantity_in_transit: 0,
+            reorder_point: 10,
+            max_stock_level: 500,
+            min_stock_level: 5,
+            safety_stock: 8,
+            economic_order_quantity: 50,
+            lead_time_days: 7,
+            storage_cost_per_unit: 0.5,
+            handling_cost_per_unit: 0.2,
+            last_counted_at: None,
+            cycle_count_frequency_days: None,
+            abc_classification: ABCClassification::A,
+            movement_velocity: MovementVelocity::Fast,
+            seasonal_factors: HashMap::new(),
+            storage_requirements: StorageRequirements::default(),
+            created_at: now,
+            updated_at: now,
+        };
+        self.items.lock().unwrap().insert((tenant_id, product_id, location_id), inventory);
+    }
+
+    fn calls(&self) -> usize {
+        self.calls.load(Ordering::SeqCst)
+    }
+
+    fn get(&self, tenant: &TenantContext, product_id: Uuid, location_id: Uuid) -> Option<LocationInventory> {
+        self.items.lock().unwrap().get(&(tenant.tenant_id.0, product_id, location_id)).cloned()
+    }
+}
+
+#[async_trait]
+impl InventoryBackend for MemoryInventory {
+    async fn location_inventory(&self, caller: &Caller, product_id: Uuid, location_id: Uuid) -> Result<LocationInventory> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        self.get(&caller.tenant, product_id, location_id)
+            .ok_or_else(|| MasterDataError::NotFoundError(format!("{}/{}", product_id, location_id)))
+    }
+
+    async fn stock_availability(&self, caller: &Caller, pairs: &[(Uuid, Uuid)]) -> Result<Vec<StockAvailability>> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        Ok(pairs
+            .iter()
+            .filter_map(|&(product_id, location_id)| self.get(&caller.tenant, product_id, location_id))
+            .map(|inventory| StockAvailability {
+                product_id: inventory.product_id,
+                location_id: inventory.location_id,
+                quantity_available: inventory.quantity_available,
+                quantity_reserved: inventory.quantity_reserved,
+                quantity_on_order: inventory.quantity_on_order,
+                quantity_in_transit: inventory.quantity_in_transit,
+                consignment_quantity: 0,
+                blocked_quantity: 0,
+                sellable: !matches!(inventory.location_type, LocationType::Quarantine),
+            })
+            .collect())
+    }
+
+    async fn record_movement(&self, caller: &Caller, request: UpdateInventoryRequest) -> Result<LocationInventory> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        let mut items = self.items.lock().unwrap();
+        let inventory = items
+            .get_mut(&(caller.tenant.tenant_id.0, request.product_id, request.location_id))
+            .ok_or(MasterDataError::NotFound)?;
+        inventory.quantity_available += request.quantity_change;
+        Ok(inventory.clone())
+    }
+
+    async fn create_reservation(&self, caller: &Caller, request: CreateReservationRequest) -> Result<InventoryReservation> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        let mut items = self.items.lock().unwrap();
+        let inventory = items
+            .get_mut(&(caller.tenant.tenant_id.0, request.product_id, request.location_id))
+            .ok_or(MasterDataError::NotFound)?;
+        if inventory.quantity_available - inventory.quantity_reserved < request.quantity {
+            return Err(MasterDataError::ValidationError {
+                field: "quantity".to_string(),
+                message: "Insufficient inventory for reservation".to_string(),
+            });
+        }
+        inventory.quantity_reserved += request.quantity;
+
+        let now = Utc::now();
+        Ok(InventoryReservation {
+            id: Uuid::new_v4(),
+            product_id: request.product_id,
+            location_id: request.location_id,
+            quantity_reserved: request.quantity,
+            reservation_status: ReservationStatus::Active,
+            priority: request.priority,
+            reference_id: request.reference_id,
+            reference_type: "manual".to_string(),
+            expiry_date: Some(request.reserved_until),
+            created_at: now,
+            updated_at: now,
+            notes: request.notes,
+            created_by: request.created_by,
+            released_at: None,
+            released_by: None,
+            quantity: request.quantity,
+            reservation_type: "manual".to_string(),
+            status: ReservationStatus::Active,
+            reserved_until: Some(request.reserved_until),
+            fulfilled_at: None,
+            fulfilled_quantity: 0,
+        })
+    }
+}
+
+struct Harness {
+    inventory: Arc<MemoryInventory>,
+    jwt: Arc<JwtService>,
+    addr: SocketAddr,
+    client: InventoryServiceClient<Channel>,
+    tenant_id: Uuid,
+}
+
+impl Harness {
+    async fn start() -> Self {
+        let inventory = Arc::new(MemoryInventory::default());
+        let jwt = Arc::new(
+            JwtService::new(&JwtConfig {
+                secret: "grpc-integration-test-secret-of-some-length".to_string(),
+                access_token_expiry: 900,
+                refresh_token_expiry: 3600,
+            })
+            .unwrap(),
+        );
+        let tenant_id = Uuid::new_v4();
+        let tenants = Arc::new(TenantContextResolver::new(
+            PgPoolOptions::new().connect_lazy("postgres://postgres@127.0.0.1:1/none").unwrap(),
+        ));
+        tenants.prime(&TenantContext { tenant_id: TenantId(tenant_id), schema_name: "acme_corp_7f3a".to_string() });
+        let config = GrpcConfig::default();
+        let service =
+            InventoryGrpcService::new(Authenticator::new(jwt.clone(), tenants), inventory.clone(), config.max_batch_size);
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = router(&config, service).unwrap();
+        tokio::spawn(server.serve_with_incoming(TcpListenerStream::new(listener)));
+
+        let client = InventoryServiceClient::connect(format!("http://{}", addr)).await.unwrap();
+        Self { inventory, jwt, addr, client, tenant_id }
+    }
+
+    fn token(&self, permissions: &[&str]) -> String {
+        self.jwt
+            .generate_token_pair(
+                &Uuid::new_v4().to_string(),
+                &self.tenant_id.to_string(),
+                vec!["service".to_string()],
+                permissions.iter().map(|p| p.to_string()).collect(),
+                None,
+            )
+            .unwrap()
+            .access_token
+    }
+
+    fn request<T>(&self, message: T, token: &str) -> Request<T> {
+        authed(message, token, self.tenant_id)
+    }
+}
+
+fn authed<T>(message: T, token: &str, tenant_id: Uuid) -> Request<T> {
+    let mut request = Request::new(message);
+    request.metadata_mut().insert("authorization", format!("Bearer {}", token).parse().unwrap());
+    request.metadata_mut().insert("x-tenant-id", tenant_id.to_string().parse().unwrap());
+    request
+}
+
+fn pair(product_id: Uuid, location_id: Uuid) -> proto::ProductLocation {
+    proto::ProductLocation {
+        product_id: product_id.to_string(),
+        location_id: location_id.to_string(),
+    }
+}
+
+#[tokio::test]
+async fn test_location_inventory_and_batch_availability() {
+    let mut harness = Harness::start().await;
+    let token = harness.token(&[INVENTORY_READ]);
+    let location = Uuid::new_v4();
+    let products: Vec<Uuid> = (0..3).map(|_| Uuid::new_v4()).collect();
+    for (i, product) in products.iter().enumerate() {
+        harness.inventory.stock(harness.tenant_id, *product, location, 10 * (i as i32 + 1));
+    }
+
+    let request = harness.request(
+        proto::GetLocationInventoryRequest {
+            product_id: products[1].to_string(),
+            location_id: location.to_string(),
+        },
+        &token,
+    );
+    let inventory = harness.client.get_location_inventory(request).await.unwrap().into_inner();
+    assert_eq!(inventory.quantity_available, 20);
+    assert_eq!(inventory.location_name, "Main warehouse");
+
+    let unknown = Uuid::new_v4();
+    let items = vec![pair(products[2], location), pair(unknown, location), pair(products[0], location)];
+    let request = harness.request(proto::BatchGetAvailabilityRequest { items }, &token);
+    let response = harness.client.batch_get_availability(request).await.unwrap().into_inner();
+
+    let found: Vec<(bool, i32)> = response.items.iter().map(|a| (a.found, a.quantity_available)).collect();
+    assert_eq!(found, vec![(true, 30), (false, 0), (true, 10)]);
+    assert_eq!(response.items[1].product_id, unknown.to_string());
+
+    let missing = harness.request(
+        proto::GetLocationInventoryRequest {
+            product_id: unknown.to_string(),
+            location_id: location.to_string(),
+        },
+        &token,
+    );
+    assert_eq!(harness.client.get_location_inventory(missing).await.unwrap_err().code(), Code::NotFound);
+}
+
+#[tokio::test]
+async fn test_tenant_and_permission_checks_apply_to_every_call() {
+    let mut harness = Harness::start().await;
+    let (product, location) = (Uuid::new_v4(), Uuid::new_v4());
+    harness.inventory.stock(harness.tenant_id, product, location, 10);
+    let read_only = harness.token(&[INVENTORY_READ]);
+
+    let get = proto::GetLocationInventoryRequest {
+        product_id: product.to_string(),
+        location_id: location.to_string(),
+    };
+    let other_tenant = harness.client.get_location_inventory(authed(get.clone(), &read_only, Uuid::new_v4())).await;
+    assert_eq!(other_tenant.unwrap_err().code(), Code::PermissionDenied);
+
+    let unauthenticated = harness.client.get_location_inventory(Request::new(get)).await;
+    assert_eq!(unauthenticated.unwrap_err().code(), Code::Unauthenticated);
+
+    let movement = proto::CreateMovementRequest {
+        product_id: product.to_string(),
+        location_id: location.to_string(),
+        quantity_change: 5,
+        movement_type: proto::MovementType::Receipt as i32,
+        ..Default::default()
+    };
+    let request = harness.request(movement, &read_only);
+    assert_eq!(harness.client.create_movement(request).await.unwrap_err().code(), Code::PermissionDenied);
+
+    assert_eq!(harness.inventory.calls(), 0, "rejected calls must not reach the inventory");
+}
+
+#[tokio::test]
+async fn test_movements_and_reservations() {
+    let mut harness = Harness::start().await;
+    let (product, location) = (Uuid::new_v4(), Uuid::new_v4());
+    harness.inventory.stock(harness.tenant_id, product, location, 10);
+    let token = harness.token(&[INVENTORY_READ, INVENTORY_WRITE]);
+
+    let movement = proto::CreateMovementRequest {
+        product_id: product.to_string(),
+        location_id: location.to_string(),
+        quantity_change: 15,
+        movement_type: proto::MovementType::Receipt as i32,
+        reference_document: Some("PO-1001".to_string()),
+        ..Default::default()
+    };
+    let inventory = harness.client.create_movement(harness.request(movement.clone(), &token)).await.unwrap().into_inner();
+    assert_eq!(inventory.quantity_available, 25);
+
+    let untyped = proto::CreateMovementRequest { movement_type: 0, ..movement };
+    let error = harness.client.create_movement(harness.request(untyped, &token)).await.unwrap_err();
+    assert_eq!(error.code(), Code::InvalidArgument);
+
+    let order = Uuid::new_v4();
+    let reserved_until = Utc::now() + chrono::Duration::days(2);
+    let reservation = proto::CreateReservationRequest {
+        product_id: product.to_string(),
+        location_id: location.to_string(),
+        quantity: 20,
+        reservation_type: proto::ReservationType::SalesOrder as i32,
+        reference_id: order.to_string(),
+        priority: proto::ReservationPriority::High as i32,
+        reserved_until: Some(prost_timestamp(reserved_until)),
+        notes: None,
+    };
+    let created = harness.client.create_reservation(harness.request(reservation.clone(), &token)).await.unwrap().into_inner();
+    assert_eq!((created.quantity, created.reference_id), (20, order.to_string()));
+    assert_eq!(created.priority, proto::ReservationPriority::High as i32);
+
+    // Only 5 of the 25 remain unreserved
+    let error = harness.client.create_reservation(harness.request(reservation, &token)).await.unwrap_err();
+    assert_eq!(error.code(), Code::InvalidArgument);
+    assert!(error.message().contains("Insufficient inventory"));
+}
+
+#[tokio::test]
+async fn test_reflection_lists_the_inventory_service() {
+    use tonic_reflection::pb::v1::{
+        server_reflection_client::ServerReflectionClient, server_reflection_request::MessageRequest,
+        server_reflection_response::MessageResponse, ServerReflectionRequest,
+    };
+
+    let harness = Harness::start().await;
+    let channel = Channel::from_shared(format!("http://{}", harness.addr)).unwrap().connect().await.unwrap();
+    let mut client = ServerReflectionClient::new(channel);
+    let request = ServerReflectionRequest {
+        host: String::new(),
+        message_request: Some(MessageRequest::ListServices(String::new())),
+    };
+    let mut responses = client.server_reflection_info(tokio_stream::iter(vec![request])).await.unwrap().into_inner();
+
+    let Some(MessageResponse::ListServicesResponse(list)) = responses.message().await.unwrap().unwrap().message_response else {
+        panic!("expected a service list");
+    };
+    assert!(list.service.iter().any(|s| s.name == "erp.inventory.v1.InventoryService"));
+}
+
+/// Documents why the picking optimizer should batch: one BatchGetAvailability
+/// call against one JSON request per pair, both served from the same backend.
+#[tokio::test]
+async fn test_batch_availability_beats_one_rest_call_per_pair() {
+    use axum::extract::{Path, State};
+    use axum::http::HeaderMap;
+
+    const PAIRS: usize = 250;
+
+    let mut harness = Harness::start().await;
+    let token = harness.token(&[INVENTORY_READ]);
+    let location = Uuid::new_v4();
+    let products: Vec<Uuid> = (0..PAIRS).map(|_| Uuid::new_v4()).collect();
+    for product in &products {
+        harness.inventory.stock(harness.tenant_id, *product, location, 40);
+    }
+
+    // The JSON equivalent: one GET per product/location pair
+    let backend: Arc<MemoryInventory> = harness.inventory.clone();
+    let app = axum::Router::new()
+        .route(
+            "/inventory/:product_id/:location_id",
+            axum::routing::get(
+                |State(backend): State<Arc<MemoryInventory>>, Path((product_id, location_id)): Path<(Uuid, Uuid)>, headers: HeaderMap| async move {
+                    let tenant_id = headers["x-tenant-id"].to_str().unwrap().parse::<Uuid>().unwrap();
+                    let caller = Caller {
+                        user_id: Uuid::new_v4(),
+                        tenant: TenantContext {
+                            tenant_id: erp_core::TenantId(tenant_id),
+                            schema_name: String::new(),
+                        },
+                        permissions: vec![INVENTORY_READ.to_string()],
+                    };
+                    let inventory = backend.location_inventory(&caller, product_id, location_id).await.unwrap();
+                    axum::Json(serde_json::json!({ "success": true, "inventory": inventory }))
+                },
+            ),
+        )
+        .with_state(backend);
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let rest_addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    let http = reqwest::Client::new();
+    let calls_before = harness.inventory.calls();
+    let started = Instant::now();
+    for product in &products {
+        let body: serde_json::Value = http
+            .get(format!("http://{}/inventory/{}/{}", rest_addr, product, location))
+            .bearer_auth(&token)
+            .header("x-tenant-id", harness.tenant_id.to_string())
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(body["inventory"]["quantity_available"], 40);
+    }
+    let rest_elapsed = started.elapsed();
+    let rest_calls = harness.inventory.calls() - calls_before;
+
+    let items = products.iter().map(|product| pair(*product, location)).collect();
+    let request = harness.request(proto::BatchGetAvailabilityRequest { items }, &token);
+    let calls_before = harness.inventory.calls();
+    let started = Instant::now();
+    let response = harness.client.batch_get_availability(request).await.unwrap().into_inner();
+    let batch_elapsed = started.elapsed();
+    let batch_calls = harness.inventory.calls() - calls_before;
+
+    assert_eq!(response.items.len(), PAIRS);
+    assert!(response.items.iter().all(|a| a.found && a.available_to_promise == 40));
+    assert_eq!((rest_calls, batch_calls), (PAIRS, 1));
+    // Timings depend on the machine; they are logged for comparison, not asserted
+    println!(
+        "{} pairs: {} REST calls took {:?}, one BatchGetAvailability took {:?}",
+        PAIRS, PAIRS, rest_elapsed, batch_elapsed
+    );
+}
+
+fn prost_timestamp(value: chrono::DateTime<Utc>) -> prost_types::Timestamp {
+    prost_types::Timestamp {
+        seconds: value.timestamp(),
+        nanos: value.timestamp_subsec_nanos() as i32,
+    }
+}
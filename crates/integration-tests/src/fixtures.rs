@@ -0,0 +1,327 @@
+//! Builders for the entities the scenarios start from
+//!
+//! Tenants and customers go through the API, the way users create them.
+//! Products and locations have no endpoint to create them and are written
+//! to the database directly. Every builder fills unique defaults, so a test
+//! only spells out what it is about.
+
+use crate::{ApiRequest, TestApp};
+use axum::http::StatusCode;
+use erp_core::config::JwtConfig;
+use erp_core::security::JwtService;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+/// Meets the default password policy
+pub const PASSWORD: &str = "Integration-Passw0rd!";
+
+fn unique(prefix: &str) -> String {
+    format!("{}-{}", prefix, &Uuid::new_v4().simple().to_string()[..8])
+}
+
+/// Registers a tenant with its first user, who gets the `admin` role
+pub struct TenantBuilder {
+    company_name: String,
+    email: String,
+}
+
+impl Default for TenantBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TenantBuilder {
+    pub fn new() -> Self {
+        let company = unique("it-company");
+        Self {
+            email: format!("admin@{}.example.com", company),
+            company_name: company,
+        }
+    }
+
+    pub fn company_name(mut self, company_name: &str) -> Self {
+        self.company_name = company_name.to_string();
+        self
+    }
+
+    pub fn email(mut self, email: &str) -> Self {
+        self.email = email.to_string();
+        self
+    }
+
+    /// `POST /auth/register`
+    pub async fn register(self, app: &TestApp) -> RegisteredTenant {
+        let body = app
+            .send(ApiRequest::post(
+                "/auth/register",
+                json!({
+                    "company_name": self.company_name,
+                    "email": self.email,
+                    "password": PASSWORD,
+                    "first_name": "Integration",
+                    "last_name": "Admin"
+                }),
+            ))
+            .await
+            .expect_success();
+
+        RegisteredTenant {
+            tenant_id: uuid_at(&body, "/tenant_id"),
+            user_id: uuid_at(&body, "/user_id"),
+            email: self.email,
+        }
+    }
+}
+
+/// A tenant registered through the API, with its admin user
+#[derive(Debug, Clone)]
+pub struct RegisteredTenant {
+    pub tenant_id: Uuid,
+    pub user_id: Uuid,
+    pub email: String,
+}
+
+impl RegisteredTenant {
+    /// `POST /auth/login` as the admin user; returns the access token
+    pub async fn login(&self, app: &TestApp) -> String {
+        let body = app
+            .send(
+                ApiRequest::post("/auth/login", json!({ "email": self.email, "password": PASSWORD }))
+                    .tenant(self.tenant_id),
+            )
+            .await
+            .expect_success();
+        body["access_token"]
+            .as_str()
+            .unwrap_or_else(|| panic!("login returned no access token: {}", body))
+            .to_string()
+    }
+
+    /// Grant `resource:action` permissions to a role of the tenant; tokens
+    /// issued by the next login carry them
+    pub async fn grant(&self, app: &TestApp, role: &str, permissions: &[&str]) {
+        let schema = self.schema_name(app).await;
+        for permission in permissions {
+            let (resource, action) = permission
+                .split_once(':')
+                .unwrap_or_else(|| panic!("permission {} is not resource:action", permission));
+            sqlx::query(&format!(
+                "INSERT INTO \"{schema}\".permissions (resource, action) VALUES ($1, $2) ON CONFLICT (resource, action) DO NOTHING"
+            ))
+            .bind(resource)
+            .bind(action)
+            .execute(&app.db().main_pool)
+            .await
+            .expect("insert permission");
+            sqlx::query(&format!(
+                "INSERT INTO \"{schema}\".role_permissions (role_id, permission_id) \
+                 SELECT r.id, p.id FROM \"{schema}\".roles r, \"{schema}\".permissions p \
+                 WHERE r.name = $1 AND p.resource = $2 AND p.action = $3 \
+                 ON CONFLICT DO NOTHING"
+            ))
+            .bind(role)
+            .bind(resource)
+            .bind(action)
+            .execute(&app.db().main_pool)
+            .await
+            .expect("grant permission to role");
+        }
+    }
+
+    /// An access token of the admin user with exactly `permissions`,
+    /// signed like the server's own
+    pub fn token(&self, app: &TestApp, permissions: &[&str]) -> String {
+        self.issue(&app.state.config.jwt, permissions)
+    }
+
+    /// Like [`token`](Self::token), but expired an hour ago
+    pub fn expired_token(&self, app: &TestApp, permissions: &[&str]) -> String {
+        let jwt = JwtConfig {
+            access_token_expiry: -3600,
+            ..app.state.config.jwt.clone()
+        };
+        self.issue(&jwt, permissions)
+    }
+
+    fn issue(&self, jwt: &JwtConfig, permissions: &[&str]) -> String {
+        JwtService::new(jwt)
+            .expect("signing key")
+            .generate_token_pair(
+                &self.user_id.to_string(),
+                &self.tenant_id.to_string(),
+                vec!["admin".to_string()],
+                permissions.iter().map(|permission| permission.to_string()).collect(),
+                None,
+            )
+            .expect("issue token")
+            .access_token
+    }
+
+    async fn schema_name(&self, app: &TestApp) -> String {
+        sqlx::query_scalar("SELECT schema_name FROM public.tenants WHERE id = $1")
+            .bind(self.tenant_id)
+            .fetch_one(&app.db().main_pool)
+            .await
+            .expect("registered tenant exists")
+    }
+}
+
+/// Creates a customer through `POST /customers` as the tenant's admin user
+pub struct CustomerBuilder {
+    legal_name: String,
+    customer_type: String,
+}
+
+impl Default for CustomerBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CustomerBuilder {
+    pub fn new() -> Self {
+        Self {
+            legal_name: unique("Customer"),
+            customer_type: "B2b".to_string(),
+        }
+    }
+
+    pub fn legal_name(mut self, legal_name: &str) -> Self {
+        self.legal_name = legal_name.to_string();
+        self
+    }
+
+    pub fn body(&self) -> Value {
+        json!({
+            "legal_name": self.legal_name,
+            "customer_type": self.customer_type
+        })
+    }
+
+    pub async fn create(self, app: &TestApp, tenant: &RegisteredTenant) -> Uuid {
+        let body = app
+            .send(
+                ApiRequest::post("/customers", self.body())
+                    .tenant(tenant.tenant_id)
+                    .token(&tenant.token(app, &[])),
+            )
+            .await
+            .expect_status(StatusCode::CREATED);
+        assert_eq!(body["success"], Value::Bool(true), "unsuccessful response: {}", body);
+        uuid_at(&body, "/customer/id")
+    }
+}
+
+/// Writes a product of the tenant
+pub struct ProductBuilder {
+    sku: String,
+    name: String,
+    base_price: i64,
+}
+
+impl Default for ProductBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProductBuilder {
+    pub fn new() -> Self {
+        let sku = unique("SKU");
+        Self {
+            name: format!("Product {}", sku),
+            sku,
+            base_price: 1999,
+        }
+    }
+
+    pub fn sku(mut self, sku: &str) -> Self {
+        self.sku = sku.to_string();
+        self
+    }
+
+    /// In cents
+    pub fn base_price(mut self, base_price: i64) -> Self {
+        self.base_price = base_price;
+        self
+    }
+
+    pub async fn insert(self, app: &TestApp, tenant: &RegisteredTenant) -> Uuid {
+        sqlx::query_scalar(
+            "INSERT INTO public.products (tenant_id, sku, name, base_price, created_by, updated_by) \
+             VALUES ($1, $2, $3, $4, $5, $5) RETURNING id",
+        )
+        .bind(tenant.tenant_id)
+        .bind(&self.sku)
+        .bind(&self.name)
+        .bind(self.base_price)
+        .bind(tenant.user_id)
+        .fetch_one(&app.db().main_pool)
+        .await
+        .expect("insert product")
+    }
+}
+
+/// Writes a stock location of the tenant
+pub struct LocationBuilder {
+    name: String,
+    location_type: String,
+}
+
+impl LocationBuilder {
+    pub fn warehouse() -> Self {
+        Self {
+            name: unique("Warehouse"),
+            location_type: "warehouse".to_string(),
+        }
+    }
+
+    /// Of the location type returns are received into
+    pub fn quarantine() -> Self {
+        Self {
+            name: unique("Quarantine"),
+            location_type: "quarantine".to_string(),
+        }
+    }
+
+    pub fn name(mut self, name: &str) -> Self {
+        self.name = name.to_string();
+        self
+    }
+
+    pub async fn insert(self, app: &TestApp, tenant: &RegisteredTenant) -> Uuid {
+        sqlx::query_scalar(
+            "INSERT INTO public.locations (tenant_id, name, code, location_type, created_by, updated_by) \
+             VALUES ($1, $2, $2, $3, $4, $4) RETURNING id",
+        )
+        .bind(tenant.tenant_id)
+        .bind(&self.name)
+        .bind(&self.location_type)
+        .bind(tenant.user_id)
+        .fetch_one(&app.db().main_pool)
+        .await
+        .expect("insert location")
+    }
+}
+
+/// Units of a product available at a location
+pub async fn stock_at(app: &TestApp, product_id: Uuid, location_id: Uuid) -> i64 {
+    sqlx::query_scalar::<_, Option<i64>>(
+        "SELECT SUM(quantity_available)::BIGINT FROM location_items WHERE product_id = $1 AND location_id = $2",
+    )
+    .bind(product_id)
+    .bind(location_id)
+    .fetch_one(&app.db().main_pool)
+    .await
+    .expect("read stock")
+    .unwrap_or(0)
+}
+
+/// The UUID at a JSON pointer of a response body
+pub fn uuid_at(body: &Value, pointer: &str) -> Uuid {
+    body.pointer(pointer)
+        .and_then(Value::as_str)
+        .and_then(|id| Uuid::parse_str(id).ok())
+        .unwrap_or_else(|| panic!("no id at {} in {}", pointer, body))
+}
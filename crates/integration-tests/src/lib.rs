@@ -0,0 +1,25 @@
+//! # ERP Integration Tests
+//!
+//! End-to-end tests of the API against a real Postgres and Redis. Each test
+//! boots the application in-process with the state the server runs on —
+//! auth, master data and the API together, no mocks — and drives it through
+//! HTTP requests.
+//!
+//! ## Running
+//!
+//! The tests are `#[ignore]`d so `cargo test --workspace` stays fast and
+//! needs no services. Start the stack and run them with the `it` alias:
+//!
+//! ```bash
+//! docker compose -f docker-compose.test.yml up -d --wait
+//! cargo it
+//! ```
+//!
+//! `ERP_IT_DATABASE_URL` and `ERP_IT_REDIS_URL` point the suite at another
+//! stack; everything in that database is wiped before each test.
+//! `ERP_IT_REQUEST_TIMEOUT_SECS` bounds every request (default 30).
+
+pub mod fixtures;
+mod harness;
+
+pub use harness::{ApiRequest, TestApp, TestResponse};
@@ -0,0 +1,99 @@
+//! Requests that must not get through: other tenants' data, expired tokens,
+//! missing permissions
+
+use axum::http::StatusCode;
+use erp_integration_tests::fixtures::{CustomerBuilder, TenantBuilder};
+use erp_integration_tests::{ApiRequest, TestApp};
+use serde_json::json;
+use uuid::Uuid;
+
+#[tokio::test]
+#[ignore = "needs the integration stack; run with `cargo it`"]
+async fn test_token_of_one_tenant_is_refused_for_another() {
+    let app = TestApp::spawn().await;
+    let acme = TenantBuilder::new().register(&app).await;
+    let globex = TenantBuilder::new().register(&app).await;
+    let acme_token = acme.token(&app, &["inventory:read", "inventory:write"]);
+
+    app.send(ApiRequest::get("/returns").tenant(acme.tenant_id).token(&acme_token))
+        .await
+        .expect_success();
+    app.send(ApiRequest::get("/returns").tenant(globex.tenant_id).token(&acme_token))
+        .await
+        .expect_status(StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+#[ignore = "needs the integration stack; run with `cargo it`"]
+async fn test_customers_are_not_visible_to_other_tenants() {
+    let app = TestApp::spawn().await;
+    let acme = TenantBuilder::new().register(&app).await;
+    let globex = TenantBuilder::new().register(&app).await;
+    let customer_id = CustomerBuilder::new().create(&app, &acme).await;
+
+    let listed = app
+        .send(ApiRequest::get("/customers").tenant(globex.tenant_id))
+        .await
+        .expect_status(StatusCode::OK);
+    assert!(
+        !listed["data"].as_array().expect("data").iter().any(|customer| customer["id"] == json!(customer_id)),
+        "customer of another tenant listed: {}",
+        listed
+    );
+
+    let fetched = app
+        .send(ApiRequest::get(&format!("/customers/{}", customer_id)).tenant(globex.tenant_id))
+        .await
+        .expect_status(StatusCode::OK);
+    assert_eq!(fetched["success"], false);
+}
+
+#[tokio::test]
+#[ignore = "needs the integration stack; run with `cargo it`"]
+async fn test_unknown_tenant_is_not_found() {
+    let app = TestApp::spawn().await;
+
+    app.send(ApiRequest::get("/customers").tenant(Uuid::new_v4()))
+        .await
+        .expect_status(StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+#[ignore = "needs the integration stack; run with `cargo it`"]
+async fn test_expired_and_missing_tokens_are_unauthorized() {
+    let app = TestApp::spawn().await;
+    let tenant = TenantBuilder::new().register(&app).await;
+    let expired = tenant.expired_token(&app, &["inventory:read"]);
+
+    app.send(ApiRequest::get("/returns").tenant(tenant.tenant_id).token(&expired))
+        .await
+        .expect_status(StatusCode::UNAUTHORIZED);
+    app.send(ApiRequest::get("/returns").tenant(tenant.tenant_id))
+        .await
+        .expect_status(StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+#[ignore = "needs the integration stack; run with `cargo it`"]
+async fn test_missing_permissions_are_forbidden() {
+    let app = TestApp::spawn().await;
+    let tenant = TenantBuilder::new().register(&app).await;
+    let read_only = tenant.token(&app, &["inventory:read"]);
+
+    app.send(
+        ApiRequest::post(
+            "/returns",
+            json!({ "customer_id": Uuid::new_v4(), "lines": [] }),
+        )
+        .tenant(tenant.tenant_id)
+        .token(&read_only),
+    )
+    .await
+    .expect_status(StatusCode::FORBIDDEN);
+    app.send(ApiRequest::get("/activity").tenant(tenant.tenant_id).token(&read_only))
+        .await
+        .expect_status(StatusCode::FORBIDDEN);
+    app.send(ApiRequest::get("/admin/tenants/health").token(&read_only))
+        .await
+        .expect_status(StatusCode::FORBIDDEN);
+}
@@ -0,0 +1,44 @@
+//! Writes record the user who made them
+
+use axum::http::StatusCode;
+use erp_integration_tests::fixtures::{CustomerBuilder, TenantBuilder};
+use erp_integration_tests::{ApiRequest, TestApp};
+use serde_json::json;
+use uuid::Uuid;
+
+#[tokio::test]
+#[ignore = "needs the integration stack; run with `cargo it`"]
+async fn test_customer_writes_record_the_signed_in_user() {
+    let app = TestApp::spawn().await;
+    let tenant = TenantBuilder::new().register(&app).await;
+    let token = tenant.token(&app, &[]);
+
+    let customer_id = CustomerBuilder::new().create(&app, &tenant).await;
+    app.send(
+        ApiRequest::put(&format!("/customers/{}", customer_id), json!({ "legal_name": "Renamed" }))
+            .tenant(tenant.tenant_id)
+            .token(&token),
+    )
+    .await
+    .expect_success();
+
+    let (created_by, modified_by): (Uuid, Uuid) =
+        sqlx::query_as("SELECT created_by, modified_by FROM customers WHERE id = $1")
+            .bind(customer_id)
+            .fetch_one(&app.db().main_pool)
+            .await
+            .expect("read customer");
+    assert_eq!(created_by, tenant.user_id);
+    assert_eq!(modified_by, tenant.user_id);
+}
+
+#[tokio::test]
+#[ignore = "needs the integration stack; run with `cargo it`"]
+async fn test_customer_writes_without_a_user_are_unauthorized() {
+    let app = TestApp::spawn().await;
+    let tenant = TenantBuilder::new().register(&app).await;
+
+    app.send(ApiRequest::post("/customers", CustomerBuilder::new().body()).tenant(tenant.tenant_id))
+        .await
+        .expect_status(StatusCode::UNAUTHORIZED);
+}
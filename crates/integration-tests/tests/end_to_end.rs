@@ -0,0 +1,95 @@
+//! A tenant from registration to its inventory reports
+
+use axum::http::StatusCode;
+use erp_integration_tests::fixtures::{stock_at, uuid_at, CustomerBuilder, LocationBuilder, ProductBuilder, TenantBuilder};
+use erp_integration_tests::{ApiRequest, TestApp};
+use serde_json::json;
+
+#[tokio::test]
+#[ignore = "needs the integration stack; run with `cargo it`"]
+async fn test_registered_tenant_books_a_customer_return_and_sees_it_reported() {
+    let app = TestApp::spawn().await;
+
+    // Register and log in; the admin role gets the inventory permissions first
+    let tenant = TenantBuilder::new().register(&app).await;
+    tenant
+        .grant(&app, "admin", &["inventory:read", "inventory:write", "audit:read"])
+        .await;
+    let token = tenant.login(&app).await;
+
+    let customer_id = CustomerBuilder::new().legal_name("Contoso Retail").create(&app, &tenant).await;
+    let customer = app
+        .send(ApiRequest::get(&format!("/customers/{}", customer_id)).tenant(tenant.tenant_id))
+        .await
+        .expect_success();
+    assert_eq!(customer["customer"]["legal_name"], "Contoso Retail");
+
+    let product_id = ProductBuilder::new().insert(&app, &tenant).await;
+    let quarantine_id = LocationBuilder::quarantine().insert(&app, &tenant).await;
+
+    // The customer sends two units back; receiving books them into quarantine
+    let created = app
+        .send(
+            ApiRequest::post(
+                "/returns",
+                json!({
+                    "customer_id": customer_id,
+                    "lines": [{ "product_id": product_id, "quantity": 2, "reason": "defective" }]
+                }),
+            )
+            .tenant(tenant.tenant_id)
+            .token(&token),
+        )
+        .await
+        .expect_status(StatusCode::CREATED);
+    let return_id = uuid_at(&created, "/return/id");
+
+    let received = app
+        .send(
+            ApiRequest::post(
+                &format!("/returns/{}/receive", return_id),
+                json!({ "quarantine_location_id": quarantine_id }),
+            )
+            .tenant(tenant.tenant_id)
+            .token(&token),
+        )
+        .await
+        .expect_success();
+    assert_eq!(received["return"]["status"], "received");
+    assert_eq!(stock_at(&app, product_id, quarantine_id).await, 2);
+
+    // Reports
+    let rates = app
+        .send(ApiRequest::get("/returns/reports/return-rates").tenant(tenant.tenant_id).token(&token))
+        .await
+        .expect_success();
+    let reported = rates["products"]
+        .as_array()
+        .expect("products")
+        .iter()
+        .find(|rate| rate["product_id"] == json!(product_id))
+        .unwrap_or_else(|| panic!("returned product missing from {}", rates));
+    assert_eq!(reported["returned_quantity"], 2);
+
+    app.send(ApiRequest::get("/activity").tenant(tenant.tenant_id).token(&token))
+        .await
+        .expect_success();
+}
+
+#[tokio::test]
+#[ignore = "needs the integration stack; run with `cargo it`"]
+async fn test_login_with_a_wrong_password_is_refused() {
+    let app = TestApp::spawn().await;
+    let tenant = TenantBuilder::new().register(&app).await;
+
+    let body = app
+        .send(
+            ApiRequest::post("/auth/login", json!({ "email": tenant.email, "password": "Wrong-Passw0rd!" }))
+                .tenant(tenant.tenant_id),
+        )
+        .await
+        .expect_status(StatusCode::OK);
+
+    assert_eq!(body["success"], false);
+    assert!(body["access_token"].is_null());
+}
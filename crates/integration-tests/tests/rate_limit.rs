@@ -0,0 +1,99 @@
+//! Sliding-window rate limits of the auth endpoints, driven over their limit
+
+use axum::http::{header, StatusCode};
+use erp_integration_tests::fixtures::{TenantBuilder, PASSWORD};
+use erp_integration_tests::{ApiRequest, TestApp, TestResponse};
+use serde_json::json;
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+/// Testing configuration with tight limits on login and password reset;
+/// client addresses are taken from `X-Forwarded-For`
+async fn spawn() -> TestApp {
+    TestApp::spawn_with(|config| {
+        config.rate_limit.trust_forwarded_for = true;
+        config.rate_limit.routes = BTreeMap::from([
+            ("/auth/login".to_string(), "3/minute".to_string()),
+            ("/auth/forgot-password".to_string(), "2/15minutes".to_string()),
+        ]);
+    })
+    .await
+}
+
+async fn login(app: &TestApp, email: &str, password: &str, address: &str, tenant_id: Option<Uuid>) -> TestResponse {
+    let mut request = ApiRequest::post("/auth/login", json!({ "email": email, "password": password }))
+        .header("x-forwarded-for", address);
+    if let Some(tenant_id) = tenant_id {
+        request = request.tenant(tenant_id);
+    }
+    app.send(request).await
+}
+
+fn retry_after(response: &TestResponse) -> u64 {
+    response.headers[header::RETRY_AFTER]
+        .to_str()
+        .ok()
+        .and_then(|secs| secs.parse().ok())
+        .unwrap_or_else(|| panic!("no Retry-After in {:?}", response.headers))
+}
+
+#[tokio::test]
+#[ignore = "needs the integration stack; run with `cargo it`"]
+async fn test_login_is_refused_past_its_limit_per_tenant_and_address() {
+    let app = spawn().await;
+    let tenant = TenantBuilder::new().register(&app).await;
+
+    for remaining in ["2", "1", "0"] {
+        let failed = login(&app, &tenant.email, "wrong-password", "203.0.113.7", Some(tenant.tenant_id)).await;
+        assert_ne!(failed.status, StatusCode::TOO_MANY_REQUESTS, "refused within the limit: {}", failed.body);
+        assert_eq!(failed.headers["x-ratelimit-limit"], "3");
+        assert_eq!(failed.headers["x-ratelimit-remaining"], remaining);
+    }
+
+    let refused = login(&app, &tenant.email, "wrong-password", "203.0.113.7", Some(tenant.tenant_id)).await;
+    assert_eq!(refused.status, StatusCode::TOO_MANY_REQUESTS);
+    assert!((1..=60).contains(&retry_after(&refused)));
+    assert_eq!(refused.headers["x-ratelimit-remaining"], "0");
+    // Even the right password waits for the window
+    let refused = login(&app, &tenant.email, PASSWORD, "203.0.113.7", Some(tenant.tenant_id)).await;
+    assert_eq!(refused.status, StatusCode::TOO_MANY_REQUESTS);
+
+    // Another address of the same tenant has a window of its own
+    tenant.login(&app).await;
+    let other = login(&app, &tenant.email, "wrong-password", "203.0.113.8", Some(tenant.tenant_id)).await;
+    assert_ne!(other.status, StatusCode::TOO_MANY_REQUESTS);
+
+    let metrics = app.state.metrics.metrics_text();
+    assert!(
+        metrics.contains(r#"rate_limit_requests_total{class="/auth/login",outcome="limited"} 2"#),
+        "limited logins not counted:\n{}",
+        metrics
+    );
+}
+
+#[tokio::test]
+#[ignore = "needs the integration stack; run with `cargo it`"]
+async fn test_password_reset_without_a_tenant_is_limited_by_address_alone() {
+    let app = spawn().await;
+    let tenant = TenantBuilder::new().register(&app).await;
+    let forgot = |address: &'static str, tenant_id: Option<Uuid>| {
+        let mut request = ApiRequest::post("/auth/forgot-password", json!({ "email": "someone@example.com" }))
+            .header("x-forwarded-for", address);
+        if let Some(tenant_id) = tenant_id {
+            request = request.tenant(tenant_id);
+        }
+        app.send(request)
+    };
+
+    for _ in 0..2 {
+        let answered = forgot("198.51.100.1", None).await;
+        assert_ne!(answered.status, StatusCode::TOO_MANY_REQUESTS, "refused within the limit: {}", answered.body);
+    }
+    let refused = forgot("198.51.100.1", None).await;
+    assert_eq!(refused.status, StatusCode::TOO_MANY_REQUESTS);
+    // The window is 15 minutes long
+    assert!(retry_after(&refused) > 60);
+
+    assert_ne!(forgot("198.51.100.2", None).await.status, StatusCode::TOO_MANY_REQUESTS);
+    assert_ne!(forgot("198.51.100.1", Some(tenant.tenant_id)).await.status, StatusCode::TOO_MANY_REQUESTS);
+}
@@ -0,0 +1,489 @@
+//! # Currency Conversion
+//!
+//! Amounts are kept in minor units (cents) of their own currency. Reports that
+//! add up amounts of several currencies convert each currency's total into the
+//! tenant's base currency with the rate effective on the valuation date, and
+//! list the original totals and the applied rates next to the converted ones.
+//!
+//! A stored [`ExchangeRate`] converts one unit of `from_currency` into `rate`
+//! units of `to_currency` from its `effective_date` on, until a later rate of
+//! the pair takes over. [`select_rate`] picks the rate for a date among the
+//! stored rates of a pair in either direction; [`cross_rate`] combines two
+//! euro reference rates into the rate between the other two currencies.
+//!
+//! A report needing a currency without a rate fails with
+//! [`MasterDataError::ExchangeRateMissing`] naming every such currency, so no
+//! row is silently left out of a total.
+//!
+//! The functions here are pure; rates are read through an
+//! [`ExchangeRateProvider`](super::provider::ExchangeRateProvider).
+
+use crate::error::{MasterDataError, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::{Decimal, RoundingStrategy};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+/// Decimal places kept for derived (inverse and cross) rates
+pub const RATE_SCALE: u32 = 10;
+
+/// Currency of the published reference rates
+pub const EURO: &str = "EUR";
+
+/// Who entered a rate
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RateSource {
+    /// Entered by the tenant
+    Manual,
+    /// Published by the European Central Bank for every tenant
+    Ecb,
+}
+
+impl RateSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RateSource::Manual => "manual",
+            RateSource::Ecb => "ecb",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "manual" => Some(RateSource::Manual),
+            "ecb" => Some(RateSource::Ecb),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExchangeRate {
+    pub id: Uuid,
+    /// `None` for published reference rates
+    pub tenant_id: Option<Uuid>,
+    /// ISO 4217 code, upper case
+    pub from_currency: String,
+    pub to_currency: String,
+    /// Units of `to_currency` per unit of `from_currency`
+    pub rate: Decimal,
+    pub effective_date: NaiveDate,
+    pub source: RateSource,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub created_by: Option<Uuid>,
+    pub updated_by: Option<Uuid>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExchangeRateRequest {
+    pub from_currency: String,
+    pub to_currency: String,
+    pub rate: Decimal,
+    pub effective_date: NaiveDate,
+}
+
+/// How an applied rate was obtained from the stored ones
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RateMethod {
+    /// A stored rate of the pair
+    Direct,
+    /// The inverse of a stored rate of the opposite pair
+    Inverse,
+    /// Two reference rates from the euro combined
+    Cross,
+}
+
+/// The rate a conversion used, reported next to converted amounts
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AppliedRate {
+    pub from_currency: String,
+    pub to_currency: String,
+    pub rate: Decimal,
+    /// Effective date of the stored rate; of the older one for cross rates
+    pub effective_date: NaiveDate,
+    pub source: RateSource,
+    pub method: RateMethod,
+}
+
+/// Upper-case ISO 4217 code of `code`
+pub fn normalize_currency(code: &str) -> Result<String> {
+    let normalized = code.trim().to_uppercase();
+    if normalized.len() != 3 || !normalized.chars().all(|c| c.is_ascii_uppercase()) {
+        return Err(MasterDataError::ValidationError {
+            field: "currency".to_string(),
+            message: format!("'{}' is not an ISO 4217 currency code", code.trim()),
+        });
+    }
+    Ok(normalized)
+}
+
+/// Normalize and check a rate before it is stored
+pub fn validate_rate(rate: &mut ExchangeRate) -> Result<()> {
+    rate.from_currency = normalize_currency(&rate.from_currency)?;
+    rate.to_currency = normalize_currency(&rate.to_currency)?;
+    if rate.from_currency == rate.to_currency {
+        return Err(MasterDataError::ValidationError {
+            field: "to_currency".to_string(),
+            message: "A rate converts between two different currencies".to_string(),
+        });
+    }
+    if rate.rate <= Decimal::ZERO {
+        return Err(MasterDataError::ValidationError {
+            field: "rate".to_string(),
+            message: "Must be greater than zero".to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// The rate converting `from` into `to` on `on`: the stored rate of the pair,
+/// in either direction, with the latest effective date not after `on`. On
+/// the same date a rate in the requested direction beats an inverted one.
+pub fn select_rate(rates: &[ExchangeRate], from: &str, to: &str, on: NaiveDate) -> Option<AppliedRate> {
+    rates
+        .iter()
+        .filter(|rate| rate.effective_date <= on)
+        .filter_map(|rate| {
+            if rate.from_currency == from && rate.to_currency == to {
+                Some((rate, RateMethod::Direct))
+            } else if rate.from_currency == to && rate.to_currency == from {
+                Some((rate, RateMethod::Inverse))
+            } else {
+                None
+            }
+        })
+        .max_by_key(|(rate, method)| (rate.effective_date, *method == RateMethod::Direct))
+        .map(|(rate, method)| AppliedRate {
+            from_currency: from.to_string(),
+            to_currency: to.to_string(),
+            rate: match method {
+                RateMethod::Inverse => (Decimal::ONE / rate.rate).round_dp(RATE_SCALE),
+                _ => rate.rate,
+            },
+            effective_date: rate.effective_date,
+            source: rate.source,
+            method,
+        })
+}
+
+/// Rate between the targets of two rates from the same currency:
+/// `pivot → from` and `pivot → to` give `from → to`
+pub fn cross_rate(from_leg: &AppliedRate, to_leg: &AppliedRate) -> AppliedRate {
+    AppliedRate {
+        from_currency: from_leg.to_currency.clone(),
+        to_currency: to_leg.to_currency.clone(),
+        rate: (to_leg.rate / from_leg.rate).round_dp(RATE_SCALE),
+        effective_date: from_leg.effective_date.min(to_leg.effective_date),
+        source: to_leg.source,
+        method: RateMethod::Cross,
+    }
+}
+
+/// `amount` minor units converted at `rate`, rounded half away from zero
+pub fn convert_amount(amount: i64, rate: Decimal) -> i64 {
+    let converted = (Decimal::from(amount) * rate).round_dp_with_strategy(0, RoundingStrategy::MidpointAwayFromZero);
+    converted
+        .to_i64()
+        .unwrap_or(if converted.is_sign_negative() { i64::MIN } else { i64::MAX })
+}
+
+/// Amounts in minor units added up per currency
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MoneyTotals(BTreeMap<String, i64>);
+
+impl MoneyTotals {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, currency: &str, amount: i64) {
+        let total = self.0.entry(currency.to_string()).or_insert(0);
+        *total = total.saturating_add(amount);
+    }
+
+    pub fn get(&self, currency: &str) -> i64 {
+        self.0.get(currency).copied().unwrap_or(0)
+    }
+
+    pub fn currencies(&self) -> impl Iterator<Item = &str> {
+        self.0.keys().map(String::as_str)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// An amount of one currency and what it became in the base currency
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CurrencyAmount {
+    pub currency: String,
+    pub amount: i64,
+    pub converted_amount: i64,
+    /// `None` for the base currency
+    pub rate: Option<Decimal>,
+    pub rate_date: Option<NaiveDate>,
+}
+
+/// A total in the base currency with the original amounts it was made of
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConvertedTotal {
+    /// The base currency
+    pub currency: String,
+    pub amount: i64,
+    pub by_currency: Vec<CurrencyAmount>,
+}
+
+/// Rates converting every currency of a report into the base currency on
+/// the valuation date
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CurrencyConversion {
+    pub base_currency: String,
+    pub valuation_date: NaiveDate,
+    /// By the currency converted from
+    pub rates: BTreeMap<String, AppliedRate>,
+}
+
+impl CurrencyConversion {
+    /// Conversion of `currencies` with `rates`; fails naming every currency
+    /// other than the base currency that has no rate
+    pub fn new<'a>(
+        base_currency: &str,
+        valuation_date: NaiveDate,
+        currencies: impl IntoIterator<Item = &'a str>,
+        rates: BTreeMap<String, AppliedRate>,
+    ) -> Result<Self> {
+        let mut missing: Vec<&str> = currencies
+            .into_iter()
+            .filter(|currency| *currency != base_currency && !rates.contains_key(*currency))
+            .collect();
+        if !missing.is_empty() {
+            missing.sort_unstable();
+            missing.dedup();
+            return Err(missing_rates(base_currency, valuation_date, &missing));
+        }
+
+        Ok(Self {
+            base_currency: base_currency.to_string(),
+            valuation_date,
+            rates,
+        })
+    }
+
+    /// `amount` of `currency` in the base currency
+    pub fn convert(&self, amount: i64, currency: &str) -> Result<i64> {
+        if currency == self.base_currency {
+            return Ok(amount);
+        }
+        self.rates
+            .get(currency)
+            .map(|rate| convert_amount(amount, rate.rate))
+            .ok_or_else(|| missing_rates(&self.base_currency, self.valuation_date, &[currency]))
+    }
+
+    /// Convert each currency's total once and add them up
+    pub fn total(&self, totals: &MoneyTotals) -> Result<ConvertedTotal> {
+        let mut by_currency = Vec::new();
+        let mut amount: i64 = 0;
+        for currency in totals.currencies() {
+            let original = totals.get(currency);
+            let converted = self.convert(original, currency)?;
+            let rate = self.rates.get(currency);
+            amount = amount.saturating_add(converted);
+            by_currency.push(CurrencyAmount {
+                currency: currency.to_string(),
+                amount: original,
+                converted_amount: converted,
+                rate: rate.map(|rate| rate.rate),
+                rate_date: rate.map(|rate| rate.effective_date),
+            });
+        }
+        Ok(ConvertedTotal {
+            currency: self.base_currency.clone(),
+            amount,
+            by_currency,
+        })
+    }
+}
+
+fn missing_rates(base_currency: &str, valuation_date: NaiveDate, currencies: &[&str]) -> MasterDataError {
+    MasterDataError::ExchangeRateMissing {
+        base_currency: base_currency.to_string(),
+        currencies: currencies.join(", "),
+        date: valuation_date.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    fn dec(value: &str) -> Decimal {
+        Decimal::from_str(value).unwrap()
+    }
+
+    fn stored(from: &str, to: &str, rate: &str, effective: &str) -> ExchangeRate {
+        let now = Utc::now();
+        ExchangeRate {
+            id: Uuid::new_v4(),
+            tenant_id: Some(Uuid::nil()),
+            from_currency: from.to_string(),
+            to_currency: to.to_string(),
+            rate: dec(rate),
+            effective_date: date(effective),
+            source: RateSource::Manual,
+            created_at: now,
+            updated_at: now,
+            created_by: None,
+            updated_by: None,
+        }
+    }
+
+    fn applied(from: &str, rate: &str, effective: &str) -> AppliedRate {
+        AppliedRate {
+            from_currency: from.to_string(),
+            to_currency: "EUR".to_string(),
+            rate: dec(rate),
+            effective_date: date(effective),
+            source: RateSource::Manual,
+            method: RateMethod::Direct,
+        }
+    }
+
+    #[test]
+    fn test_select_rate_uses_the_latest_rate_not_after_the_date() {
+        let rates = vec![
+            stored("CHF", "EUR", "1.02", "2025-01-01"),
+            stored("CHF", "EUR", "1.05", "2025-03-01"),
+            stored("CHF", "EUR", "1.08", "2025-06-01"),
+        ];
+
+        let march = select_rate(&rates, "CHF", "EUR", date("2025-05-31")).unwrap();
+        assert_eq!(march.rate, dec("1.05"));
+        assert_eq!(march.effective_date, date("2025-03-01"));
+
+        let effective_day = select_rate(&rates, "CHF", "EUR", date("2025-06-01")).unwrap();
+        assert_eq!(effective_day.rate, dec("1.08"));
+
+        assert!(select_rate(&rates, "CHF", "EUR", date("2024-12-31")).is_none());
+    }
+
+    #[test]
+    fn test_select_rate_inverts_the_opposite_pair() {
+        let rates = vec![stored("EUR", "CHF", "0.8", "2025-01-01")];
+
+        let rate = select_rate(&rates, "CHF", "EUR", date("2025-02-01")).unwrap();
+
+        assert_eq!(rate.method, RateMethod::Inverse);
+        assert_eq!(rate.rate, dec("1.25"));
+        assert_eq!(rate.from_currency, "CHF");
+    }
+
+    #[test]
+    fn test_select_rate_prefers_the_later_rate_over_the_direction() {
+        let mut rates = vec![
+            stored("CHF", "EUR", "1.02", "2025-01-01"),
+            stored("EUR", "CHF", "0.8", "2025-02-01"),
+        ];
+
+        let later = select_rate(&rates, "CHF", "EUR", date("2025-03-01")).unwrap();
+        assert_eq!(later.method, RateMethod::Inverse);
+        assert_eq!(later.effective_date, date("2025-02-01"));
+
+        // On the same date the rate in the requested direction wins
+        rates.push(stored("CHF", "EUR", "1.30", "2025-02-01"));
+        let same_day = select_rate(&rates, "CHF", "EUR", date("2025-03-01")).unwrap();
+        assert_eq!(same_day.method, RateMethod::Direct);
+        assert_eq!(same_day.rate, dec("1.30"));
+    }
+
+    #[test]
+    fn test_cross_rate_goes_through_the_common_currency() {
+        let eur_chf = AppliedRate {
+            to_currency: "CHF".to_string(),
+            from_currency: "EUR".to_string(),
+            ..applied("EUR", "0.9", "2025-04-02")
+        };
+        let eur_usd = AppliedRate {
+            to_currency: "USD".to_string(),
+            from_currency: "EUR".to_string(),
+            ..applied("EUR", "1.08", "2025-04-01")
+        };
+
+        let chf_usd = cross_rate(&eur_chf, &eur_usd);
+
+        assert_eq!(chf_usd.from_currency, "CHF");
+        assert_eq!(chf_usd.to_currency, "USD");
+        assert_eq!(chf_usd.rate, dec("1.2"));
+        assert_eq!(chf_usd.effective_date, date("2025-04-01"));
+        assert_eq!(chf_usd.method, RateMethod::Cross);
+    }
+
+    #[test]
+    fn test_convert_amount_rounds_half_away_from_zero() {
+        assert_eq!(convert_amount(1000, dec("1.0505")), 1051);
+        assert_eq!(convert_amount(-1000, dec("1.0505")), -1051);
+        assert_eq!(convert_amount(333, dec("0.5")), 167);
+    }
+
+    #[test]
+    fn test_total_converts_each_currency_into_the_base_currency() {
+        let mut totals = MoneyTotals::new();
+        totals.add("EUR", 10_000);
+        totals.add("CHF", 5_000);
+        totals.add("CHF", 5_000);
+        let rates = BTreeMap::from([("CHF".to_string(), applied("CHF", "1.05", "2025-03-01"))]);
+        let conversion = CurrencyConversion::new("EUR", date("2025-03-15"), totals.currencies(), rates).unwrap();
+
+        let total = conversion.total(&totals).unwrap();
+
+        assert_eq!(total.currency, "EUR");
+        assert_eq!(total.amount, 10_000 + 10_500);
+        let chf = total.by_currency.iter().find(|amount| amount.currency == "CHF").unwrap();
+        assert_eq!(chf.amount, 10_000);
+        assert_eq!(chf.converted_amount, 10_500);
+        assert_eq!(chf.rate, Some(dec("1.05")));
+        assert_eq!(chf.rate_date, Some(date("2025-03-01")));
+        let eur = total.by_currency.iter().find(|amount| amount.currency == "EUR").unwrap();
+        assert_eq!((eur.amount, eur.converted_amount, eur.rate), (10_000, 10_000, None));
+    }
+
+    #[test]
+    fn test_missing_rates_are_all_named() {
+        let rates = BTreeMap::from([("CHF".to_string(), applied("CHF", "1.05", "2025-03-01"))]);
+
+        let error = CurrencyConversion::new("EUR", date("2025-03-15"), ["USD", "EUR", "CHF", "GBP", "USD"], rates).unwrap_err();
+
+        match error {
+            MasterDataError::ExchangeRateMissing { base_currency, currencies, date } => {
+                assert_eq!(base_currency, "EUR");
+                assert_eq!(currencies, "GBP, USD");
+                assert_eq!(date, "2025-03-15");
+            }
+            other => panic!("unexpected error: {}", other),
+        }
+    }
+
+    #[test]
+    fn test_validate_rate_normalizes_codes() {
+        let mut rate = stored(" chf", "eur ", "1.05", "2025-01-01");
+        validate_rate(&mut rate).unwrap();
+        assert_eq!((rate.from_currency.as_str(), rate.to_currency.as_str()), ("CHF", "EUR"));
+
+        let mut same = stored("EUR", "eur", "1", "2025-01-01");
+        assert!(validate_rate(&mut same).is_err());
+        let mut negative = stored("CHF", "EUR", "-1", "2025-01-01");
+        assert!(validate_rate(&mut negative).is_err());
+        assert!(normalize_currency("EURO").is_err());
+        assert!(normalize_currency("E1R").is_err());
+    }
+}
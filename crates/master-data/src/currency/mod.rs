@@ -0,0 +1,24 @@
+//! # Currencies and Exchange Rates
+//!
+//! Every tenant has a base currency its reports are stated in. Products keep
+//! their prices in their own currency; the valuation, KPI, margin and
+//! dashboard reports convert their totals into the base currency with the
+//! rate effective on the valuation date and show the original amounts and
+//! applied rates alongside (see [`conversion`]).
+//!
+//! Rates come from an [`ExchangeRateProvider`]: the tenant's own rates
+//! ([`ManualRateProvider`]), falling back to the euro reference rates the ECB
+//! fetcher publishes ([`EcbRateProvider`]) where that job is enabled.
+
+pub mod conversion;
+pub mod provider;
+pub mod repository;
+pub mod service;
+
+pub use conversion::{
+    AppliedRate, ConvertedTotal, CurrencyAmount, CurrencyConversion, ExchangeRate, ExchangeRateRequest, MoneyTotals,
+    RateMethod, RateSource, EURO,
+};
+pub use provider::{resolve_conversion, EcbRateProvider, ExchangeRateProvider, ManualRateProvider};
+pub use repository::{ExchangeRateRepository, PostgresExchangeRateRepository};
+pub use service::{DefaultExchangeRateService, ExchangeRateService};
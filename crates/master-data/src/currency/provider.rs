@@ -0,0 +1,263 @@
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::currency::conversion::{cross_rate, select_rate, AppliedRate, CurrencyConversion, EURO};
+use crate::currency::repository::ExchangeRateRepository;
+use crate::error::Result;
+
+/// Where conversions get their rates from
+#[async_trait]
+pub trait ExchangeRateProvider: Send + Sync {
+    /// Rate converting `from` into `to` effective on `on`, if there is one
+    async fn rate(&self, tenant_id: Uuid, from: &str, to: &str, on: NaiveDate) -> Result<Option<AppliedRate>>;
+}
+
+/// The rates a tenant entered itself; pairs without one are asked of the
+/// fallback, if any
+pub struct ManualRateProvider {
+    repository: Arc<dyn ExchangeRateRepository>,
+    fallback: Option<Arc<dyn ExchangeRateProvider>>,
+}
+
+impl ManualRateProvider {
+    pub fn new(repository: Arc<dyn ExchangeRateRepository>) -> Self {
+        Self {
+            repository,
+            fallback: None,
+        }
+    }
+
+    pub fn with_fallback(mut self, fallback: Arc<dyn ExchangeRateProvider>) -> Self {
+        self.fallback = Some(fallback);
+        self
+    }
+}
+
+#[async_trait]
+impl ExchangeRateProvider for ManualRateProvider {
+    async fn rate(&self, tenant_id: Uuid, from: &str, to: &str, on: NaiveDate) -> Result<Option<AppliedRate>> {
+        let rates = self.repository.rates_between(Some(tenant_id), from, to, on).await?;
+        if let Some(rate) = select_rate(&rates, from, to, on) {
+            return Ok(Some(rate));
+        }
+        match &self.fallback {
+            Some(fallback) => fallback.rate(tenant_id, from, to, on).await,
+            None => Ok(None),
+        }
+    }
+}
+
+/// The euro reference rates published by the ECB fetcher; pairs without the
+/// euro are crossed through it
+pub struct EcbRateProvider {
+    repository: Arc<dyn ExchangeRateRepository>,
+}
+
+impl EcbRateProvider {
+    pub fn new(repository: Arc<dyn ExchangeRateRepository>) -> Self {
+        Self { repository }
+    }
+
+    /// Reference rate converting euros into `to`
+    async fn euro_rate(&self, to: &str, on: NaiveDate) -> Result<Option<AppliedRate>> {
+        let rates = self.repository.rates_between(None, EURO, to, on).await?;
+        Ok(select_rate(&rates, EURO, to, on))
+    }
+}
+
+#[async_trait]
+impl ExchangeRateProvider for EcbRateProvider {
+    async fn rate(&self, _tenant_id: Uuid, from: &str, to: &str, on: NaiveDate) -> Result<Option<AppliedRate>> {
+        if from == EURO || to == EURO {
+            let rates = self.repository.rates_between(None, from, to, on).await?;
+            return Ok(select_rate(&rates, from, to, on));
+        }
+        let (Some(from_leg), Some(to_leg)) = (self.euro_rate(from, on).await?, self.euro_rate(to, on).await?) else {
+            return Ok(None);
+        };
+        Ok(Some(cross_rate(&from_leg, &to_leg)))
+    }
+}
+
+/// Rates converting every one of `currencies` into `base_currency` on `on`;
+/// fails naming all currencies without a rate
+pub async fn resolve_conversion(
+    provider: &dyn ExchangeRateProvider,
+    tenant_id: Uuid,
+    base_currency: &str,
+    currencies: &[String],
+    on: NaiveDate,
+) -> Result<CurrencyConversion> {
+    let mut rates = BTreeMap::new();
+    for currency in currencies {
+        if currency == base_currency || rates.contains_key(currency) {
+            continue;
+        }
+        if let Some(rate) = provider.rate(tenant_id, currency, base_currency, on).await? {
+            rates.insert(currency.clone(), rate);
+        }
+    }
+    CurrencyConversion::new(base_currency, on, currencies.iter().map(String::as_str), rates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::currency::conversion::{ExchangeRate, RateMethod, RateSource};
+    use crate::error::MasterDataError;
+    use chrono::Utc;
+    use rust_decimal::Decimal;
+    use std::str::FromStr;
+    use std::sync::Mutex;
+
+    /// Rates kept in memory, read like the Postgres repository reads them
+    #[derive(Default)]
+    struct InMemoryRates {
+        rates: Mutex<Vec<ExchangeRate>>,
+    }
+
+    impl InMemoryRates {
+        fn with(rates: Vec<ExchangeRate>) -> Arc<Self> {
+            Arc::new(Self { rates: Mutex::new(rates) })
+        }
+    }
+
+    #[async_trait]
+    impl ExchangeRateRepository for InMemoryRates {
+        async fn list_rates(&self, tenant_id: Uuid, _currency: Option<&str>) -> Result<Vec<ExchangeRate>> {
+            let rates = self.rates.lock().unwrap();
+            Ok(rates.iter().filter(|rate| rate.tenant_id == Some(tenant_id)).cloned().collect())
+        }
+
+        async fn get_rate(&self, tenant_id: Uuid, id: Uuid) -> Result<Option<ExchangeRate>> {
+            let rates = self.rates.lock().unwrap();
+            Ok(rates.iter().find(|rate| rate.tenant_id == Some(tenant_id) && rate.id == id).cloned())
+        }
+
+        async fn save_rate(&self, rate: &ExchangeRate) -> Result<()> {
+            self.rates.lock().unwrap().push(rate.clone());
+            Ok(())
+        }
+
+        async fn delete_rate(&self, _tenant_id: Uuid, _id: Uuid) -> Result<bool> {
+            Ok(false)
+        }
+
+        async fn rates_between(&self, tenant_id: Option<Uuid>, a: &str, b: &str, on: NaiveDate) -> Result<Vec<ExchangeRate>> {
+            let rates = self.rates.lock().unwrap();
+            Ok(rates
+                .iter()
+                .filter(|rate| rate.tenant_id == tenant_id && rate.effective_date <= on)
+                .filter(|rate| {
+                    (rate.from_currency == a && rate.to_currency == b) || (rate.from_currency == b && rate.to_currency == a)
+                })
+                .cloned()
+                .collect())
+        }
+
+        async fn publish_rates(&self, rates: &[ExchangeRate]) -> Result<u64> {
+            self.rates.lock().unwrap().extend_from_slice(rates);
+            Ok(rates.len() as u64)
+        }
+
+        async fn base_currency(&self, _tenant_id: Uuid) -> Result<String> {
+            Ok("EUR".to_string())
+        }
+
+        async fn set_base_currency(&self, _tenant_id: Uuid, _currency: &str) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    fn rate(tenant_id: Option<Uuid>, from: &str, to: &str, value: &str, effective: &str) -> ExchangeRate {
+        let now = Utc::now();
+        ExchangeRate {
+            id: Uuid::new_v4(),
+            tenant_id,
+            from_currency: from.to_string(),
+            to_currency: to.to_string(),
+            rate: Decimal::from_str(value).unwrap(),
+            effective_date: date(effective),
+            source: if tenant_id.is_some() { RateSource::Manual } else { RateSource::Ecb },
+            created_at: now,
+            updated_at: now,
+            created_by: None,
+            updated_by: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_manual_rates_win_over_published_ones() {
+        let tenant_id = Uuid::new_v4();
+        let repository = InMemoryRates::with(vec![
+            rate(Some(tenant_id), "CHF", "EUR", "1.10", "2025-01-01"),
+            rate(None, "EUR", "CHF", "0.95", "2025-03-01"),
+            rate(None, "EUR", "USD", "1.08", "2025-03-01"),
+        ]);
+        let provider = ManualRateProvider::new(repository.clone()).with_fallback(Arc::new(EcbRateProvider::new(repository)));
+
+        let chf = provider.rate(tenant_id, "CHF", "EUR", date("2025-03-15")).await.unwrap().unwrap();
+        assert_eq!(chf.source, RateSource::Manual);
+        assert_eq!(chf.rate, Decimal::from_str("1.10").unwrap());
+
+        let usd = provider.rate(tenant_id, "USD", "EUR", date("2025-03-15")).await.unwrap().unwrap();
+        assert_eq!(usd.source, RateSource::Ecb);
+        assert_eq!(usd.method, RateMethod::Inverse);
+    }
+
+    #[tokio::test]
+    async fn test_published_rates_are_crossed_through_the_euro() {
+        let repository = InMemoryRates::with(vec![
+            rate(None, "EUR", "CHF", "0.9", "2025-03-03"),
+            rate(None, "EUR", "USD", "1.08", "2025-03-03"),
+        ]);
+        let provider = EcbRateProvider::new(repository);
+
+        let chf_usd = provider.rate(Uuid::new_v4(), "CHF", "USD", date("2025-03-04")).await.unwrap().unwrap();
+
+        assert_eq!(chf_usd.method, RateMethod::Cross);
+        assert_eq!(chf_usd.rate, Decimal::from_str("1.2").unwrap());
+        assert!(provider.rate(Uuid::new_v4(), "CHF", "GBP", date("2025-03-04")).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_resolve_conversion_uses_the_rate_of_the_valuation_date() {
+        let tenant_id = Uuid::new_v4();
+        let repository = InMemoryRates::with(vec![
+            rate(Some(tenant_id), "CHF", "EUR", "1.02", "2024-12-31"),
+            rate(Some(tenant_id), "CHF", "EUR", "1.06", "2025-06-30"),
+        ]);
+        let provider = ManualRateProvider::new(repository);
+        let currencies = vec!["CHF".to_string(), "EUR".to_string()];
+
+        let year_end = resolve_conversion(&provider, tenant_id, "EUR", &currencies, date("2025-03-31")).await.unwrap();
+        assert_eq!(year_end.convert(10_000, "CHF").unwrap(), 10_200);
+        assert_eq!(year_end.rates["CHF"].effective_date, date("2024-12-31"));
+
+        let half_year = resolve_conversion(&provider, tenant_id, "EUR", &currencies, date("2025-07-01")).await.unwrap();
+        assert_eq!(half_year.convert(10_000, "CHF").unwrap(), 10_600);
+    }
+
+    #[tokio::test]
+    async fn test_resolve_conversion_fails_without_a_rate() {
+        let tenant_id = Uuid::new_v4();
+        let repository = InMemoryRates::with(vec![rate(Some(tenant_id), "CHF", "EUR", "1.02", "2025-01-01")]);
+        let provider = ManualRateProvider::new(repository);
+        let currencies = vec!["CHF".to_string(), "USD".to_string()];
+
+        let error = resolve_conversion(&provider, tenant_id, "EUR", &currencies, date("2024-12-31")).await.unwrap_err();
+
+        assert!(
+            matches!(&error, MasterDataError::ExchangeRateMissing { currencies, .. } if currencies == "CHF, USD"),
+            "unexpected error: {}",
+            error
+        );
+    }
+}
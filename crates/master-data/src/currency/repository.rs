@@ -0,0 +1,206 @@
+use async_trait::async_trait;
+use chrono::NaiveDate;
+use sqlx::{PgPool, Row};
+use uuid::Uuid;
+
+use crate::currency::conversion::{ExchangeRate, RateSource};
+use crate::error::{MasterDataError, Result};
+
+/// Columns of `exchange_rates` read into an [`ExchangeRate`]
+const RATE_COLUMNS: &str = "id, tenant_id, from_currency::TEXT AS from_currency, to_currency::TEXT AS to_currency, \
+     rate, effective_date, source, created_at, updated_at, created_by, updated_by";
+
+/// Exchange rates and tenant base currencies
+#[async_trait]
+pub trait ExchangeRateRepository: Send + Sync {
+    /// The tenant's own rates, latest first; with `currency`, only those converting from or into it
+    async fn list_rates(&self, tenant_id: Uuid, currency: Option<&str>) -> Result<Vec<ExchangeRate>>;
+
+    async fn get_rate(&self, tenant_id: Uuid, id: Uuid) -> Result<Option<ExchangeRate>>;
+
+    /// Insert or update a tenant rate by id; fails with `DuplicateExchangeRate`
+    /// if the pair already has another rate on that day
+    async fn save_rate(&self, rate: &ExchangeRate) -> Result<()>;
+
+    async fn delete_rate(&self, tenant_id: Uuid, id: Uuid) -> Result<bool>;
+
+    /// Latest rate in each direction between `a` and `b` effective on or
+    /// before `on`: the tenant's own, or the published ones for `None`
+    async fn rates_between(&self, tenant_id: Option<Uuid>, a: &str, b: &str, on: NaiveDate) -> Result<Vec<ExchangeRate>>;
+
+    /// Store published reference rates, replacing those of the same pair and day
+    async fn publish_rates(&self, rates: &[ExchangeRate]) -> Result<u64>;
+
+    async fn base_currency(&self, tenant_id: Uuid) -> Result<String>;
+
+    async fn set_base_currency(&self, tenant_id: Uuid, currency: &str) -> Result<()>;
+}
+
+pub struct PostgresExchangeRateRepository {
+    pool: PgPool,
+}
+
+impl PostgresExchangeRateRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+fn exchange_rate(row: &sqlx::postgres::PgRow) -> Result<ExchangeRate> {
+    let source: String = row.try_get("source")?;
+    Ok(ExchangeRate {
+        id: row.try_get("id")?,
+        tenant_id: row.try_get("tenant_id")?,
+        from_currency: row.try_get("from_currency")?,
+        to_currency: row.try_get("to_currency")?,
+        rate: row.try_get("rate")?,
+        effective_date: row.try_get("effective_date")?,
+        source: RateSource::parse(&source)
+            .ok_or_else(|| MasterDataError::DatabaseError(format!("Unknown exchange rate source: {}", source)))?,
+        created_at: row.try_get("created_at")?,
+        updated_at: row.try_get("updated_at")?,
+        created_by: row.try_get("created_by")?,
+        updated_by: row.try_get("updated_by")?,
+    })
+}
+
+fn is_unique_violation(e: &sqlx::Error) -> bool {
+    matches!(e, sqlx::Error::Database(db) if db.code().as_deref() == Some("23505"))
+}
+
+#[async_trait]
+impl ExchangeRateRepository for PostgresExchangeRateRepository {
+    async fn list_rates(&self, tenant_id: Uuid, currency: Option<&str>) -> Result<Vec<ExchangeRate>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {RATE_COLUMNS} FROM public.exchange_rates \
+             WHERE tenant_id = $1 AND ($2::TEXT IS NULL OR from_currency = $2 OR to_currency = $2) \
+             ORDER BY effective_date DESC, from_currency, to_currency"
+        ))
+        .bind(tenant_id)
+        .bind(currency)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(exchange_rate).collect()
+    }
+
+    async fn get_rate(&self, tenant_id: Uuid, id: Uuid) -> Result<Option<ExchangeRate>> {
+        let row = sqlx::query(&format!(
+            "SELECT {RATE_COLUMNS} FROM public.exchange_rates WHERE tenant_id = $1 AND id = $2"
+        ))
+        .bind(tenant_id)
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.as_ref().map(exchange_rate).transpose()
+    }
+
+    async fn save_rate(&self, rate: &ExchangeRate) -> Result<()> {
+        let result = sqlx::query(
+            "INSERT INTO public.exchange_rates \
+             (id, tenant_id, from_currency, to_currency, rate, effective_date, source, created_at, updated_at, created_by, updated_by) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11) \
+             ON CONFLICT (id) DO UPDATE SET \
+                 from_currency = EXCLUDED.from_currency, to_currency = EXCLUDED.to_currency, rate = EXCLUDED.rate, \
+                 effective_date = EXCLUDED.effective_date, updated_at = EXCLUDED.updated_at, updated_by = EXCLUDED.updated_by \
+             WHERE exchange_rates.tenant_id = EXCLUDED.tenant_id",
+        )
+        .bind(rate.id)
+        .bind(rate.tenant_id)
+        .bind(&rate.from_currency)
+        .bind(&rate.to_currency)
+        .bind(rate.rate)
+        .bind(rate.effective_date)
+        .bind(rate.source.as_str())
+        .bind(rate.created_at)
+        .bind(rate.updated_at)
+        .bind(rate.created_by)
+        .bind(rate.updated_by)
+        .execute(&self.pool)
+        .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) if is_unique_violation(&e) => Err(MasterDataError::DuplicateExchangeRate {
+                pair: format!("{}/{}", rate.from_currency, rate.to_currency),
+                date: rate.effective_date.to_string(),
+            }),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn delete_rate(&self, tenant_id: Uuid, id: Uuid) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM public.exchange_rates WHERE tenant_id = $1 AND id = $2")
+            .bind(tenant_id)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn rates_between(&self, tenant_id: Option<Uuid>, a: &str, b: &str, on: NaiveDate) -> Result<Vec<ExchangeRate>> {
+        let rows = sqlx::query(&format!(
+            "SELECT DISTINCT ON (from_currency) {RATE_COLUMNS} FROM public.exchange_rates \
+             WHERE tenant_id IS NOT DISTINCT FROM $1 \
+               AND ((from_currency = $2 AND to_currency = $3) OR (from_currency = $3 AND to_currency = $2)) \
+               AND effective_date <= $4 \
+             ORDER BY from_currency, effective_date DESC"
+        ))
+        .bind(tenant_id)
+        .bind(a)
+        .bind(b)
+        .bind(on)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(exchange_rate).collect()
+    }
+
+    async fn publish_rates(&self, rates: &[ExchangeRate]) -> Result<u64> {
+        let mut tx = self.pool.begin().await?;
+        let mut stored = 0;
+        for rate in rates {
+            stored += sqlx::query(
+                "INSERT INTO public.exchange_rates \
+                 (id, tenant_id, from_currency, to_currency, rate, effective_date, source, created_at, updated_at) \
+                 VALUES ($1, NULL, $2, $3, $4, $5, $6, $7, $8) \
+                 ON CONFLICT (COALESCE(tenant_id, '00000000-0000-0000-0000-000000000000'::uuid), from_currency, to_currency, effective_date) \
+                 DO UPDATE SET rate = EXCLUDED.rate, updated_at = EXCLUDED.updated_at",
+            )
+            .bind(rate.id)
+            .bind(&rate.from_currency)
+            .bind(&rate.to_currency)
+            .bind(rate.rate)
+            .bind(rate.effective_date)
+            .bind(rate.source.as_str())
+            .bind(rate.created_at)
+            .bind(rate.updated_at)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        }
+        tx.commit().await?;
+        Ok(stored)
+    }
+
+    async fn base_currency(&self, tenant_id: Uuid) -> Result<String> {
+        sqlx::query_scalar("SELECT base_currency::TEXT FROM public.tenants WHERE id = $1")
+            .bind(tenant_id)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or_else(|| MasterDataError::NotFoundError(format!("Tenant {}", tenant_id)))
+    }
+
+    async fn set_base_currency(&self, tenant_id: Uuid, currency: &str) -> Result<()> {
+        let result = sqlx::query("UPDATE public.tenants SET base_currency = $2, updated_at = NOW() WHERE id = $1")
+            .bind(tenant_id)
+            .bind(currency)
+            .execute(&self.pool)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(MasterDataError::NotFoundError(format!("Tenant {}", tenant_id)));
+        }
+        Ok(())
+    }
+}
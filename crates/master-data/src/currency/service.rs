@@ -0,0 +1,149 @@
+use async_trait::async_trait;
+use chrono::{NaiveDate, Utc};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::currency::conversion::*;
+use crate::currency::provider::ExchangeRateProvider;
+use crate::currency::repository::ExchangeRateRepository;
+use crate::error::{MasterDataError, Result};
+use crate::types::TenantContext;
+
+/// Base currency of a tenant and the exchange rates it entered
+#[async_trait]
+pub trait ExchangeRateService: Send + Sync {
+    async fn base_currency(&self) -> Result<String>;
+
+    /// Reports are converted into this currency from now on
+    async fn set_base_currency(&self, currency: &str) -> Result<String>;
+
+    /// With `currency`, only rates converting from or into it
+    async fn list_rates(&self, currency: Option<&str>) -> Result<Vec<ExchangeRate>>;
+
+    async fn get_rate(&self, id: Uuid) -> Result<ExchangeRate>;
+
+    /// Fails with `DuplicateExchangeRate` if the pair has a rate on that day
+    async fn create_rate(&self, request: ExchangeRateRequest) -> Result<ExchangeRate>;
+
+    /// Same checks as [`create_rate`](Self::create_rate)
+    async fn update_rate(&self, id: Uuid, request: ExchangeRateRequest) -> Result<ExchangeRate>;
+
+    async fn delete_rate(&self, id: Uuid) -> Result<()>;
+
+    /// The rate reports would apply converting `from` into `to` on `on`
+    async fn effective_rate(&self, from: &str, to: &str, on: NaiveDate) -> Result<AppliedRate>;
+}
+
+pub struct DefaultExchangeRateService {
+    repository: Arc<dyn ExchangeRateRepository>,
+    provider: Arc<dyn ExchangeRateProvider>,
+    tenant_context: TenantContext,
+}
+
+impl DefaultExchangeRateService {
+    pub fn new(
+        repository: Arc<dyn ExchangeRateRepository>,
+        provider: Arc<dyn ExchangeRateProvider>,
+        tenant_context: TenantContext,
+    ) -> Self {
+        Self {
+            repository,
+            provider,
+            tenant_context,
+        }
+    }
+
+    async fn save(&self, mut rate: ExchangeRate) -> Result<ExchangeRate> {
+        validate_rate(&mut rate)?;
+        self.repository.save_rate(&rate).await?;
+        Ok(rate)
+    }
+}
+
+#[async_trait]
+impl ExchangeRateService for DefaultExchangeRateService {
+    async fn base_currency(&self) -> Result<String> {
+        self.repository.base_currency(self.tenant_context.tenant_id).await
+    }
+
+    async fn set_base_currency(&self, currency: &str) -> Result<String> {
+        let currency = normalize_currency(currency)?;
+        self.repository
+            .set_base_currency(self.tenant_context.tenant_id, &currency)
+            .await?;
+        Ok(currency)
+    }
+
+    async fn list_rates(&self, currency: Option<&str>) -> Result<Vec<ExchangeRate>> {
+        let currency = currency.map(normalize_currency).transpose()?;
+        self.repository
+            .list_rates(self.tenant_context.tenant_id, currency.as_deref())
+            .await
+    }
+
+    async fn get_rate(&self, id: Uuid) -> Result<ExchangeRate> {
+        self.repository
+            .get_rate(self.tenant_context.tenant_id, id)
+            .await?
+            .ok_or_else(|| MasterDataError::NotFoundError(format!("Exchange rate {}", id)))
+    }
+
+    async fn create_rate(&self, request: ExchangeRateRequest) -> Result<ExchangeRate> {
+        let now = Utc::now();
+        let user_id = Some(self.tenant_context.user_id);
+        self.save(ExchangeRate {
+            id: Uuid::new_v4(),
+            tenant_id: Some(self.tenant_context.tenant_id),
+            from_currency: request.from_currency,
+            to_currency: request.to_currency,
+            rate: request.rate,
+            effective_date: request.effective_date,
+            source: RateSource::Manual,
+            created_at: now,
+            updated_at: now,
+            created_by: user_id,
+            updated_by: user_id,
+        })
+        .await
+    }
+
+    async fn update_rate(&self, id: Uuid, request: ExchangeRateRequest) -> Result<ExchangeRate> {
+        let current = self.get_rate(id).await?;
+        self.save(ExchangeRate {
+            from_currency: request.from_currency,
+            to_currency: request.to_currency,
+            rate: request.rate,
+            effective_date: request.effective_date,
+            updated_at: Utc::now(),
+            updated_by: Some(self.tenant_context.user_id),
+            ..current
+        })
+        .await
+    }
+
+    async fn delete_rate(&self, id: Uuid) -> Result<()> {
+        if !self.repository.delete_rate(self.tenant_context.tenant_id, id).await? {
+            return Err(MasterDataError::NotFoundError(format!("Exchange rate {}", id)));
+        }
+        Ok(())
+    }
+
+    async fn effective_rate(&self, from: &str, to: &str, on: NaiveDate) -> Result<AppliedRate> {
+        let from = normalize_currency(from)?;
+        let to = normalize_currency(to)?;
+        if from == to {
+            return Err(MasterDataError::ValidationError {
+                field: "to".to_string(),
+                message: "A rate converts between two different currencies".to_string(),
+            });
+        }
+        self.provider
+            .rate(self.tenant_context.tenant_id, &from, &to, on)
+            .await?
+            .ok_or(MasterDataError::ExchangeRateMissing {
+                base_currency: to,
+                currencies: from,
+                date: on.to_string(),
+            })
+    }
+}
@@ -5,14 +5,82 @@
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use uuid::Uuid;
 
 use crate::customer::events::CustomerEvent;
 use crate::customer::model::*;
+use crate::customer::repository::CustomerMergeRepository;
 use crate::error::{MasterDataError, Result};
 use crate::types::*;
 
+/// Identifier of a customer
+pub type CustomerId = Uuid;
+
+/// Fields of a merged customer that can be taken from either record
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeField {
+    LegalName,
+    CustomerType,
+    CurrencyCode,
+    CreditLimit,
+    PaymentTerms,
+    TaxExempt,
+}
+
+impl MergeField {
+    pub const ALL: [MergeField; 6] = [
+        MergeField::LegalName,
+        MergeField::CustomerType,
+        MergeField::CurrencyCode,
+        MergeField::CreditLimit,
+        MergeField::PaymentTerms,
+        MergeField::TaxExempt,
+    ];
+}
+
+/// Record of a merge a field is taken from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeSide {
+    Source,
+    Target,
+}
+
+/// Per-field choice of a manual merge; fields not named keep the target's value
+pub type MergeFieldMap = BTreeMap<MergeField, MergeSide>;
+
+/// How the fields of two merged customers are combined
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "strategy", content = "fields", rename_all = "snake_case")]
+pub enum MergeStrategy {
+    /// Every field keeps the target's value
+    KeepTarget,
+    /// Every field takes the source's value
+    KeepSource,
+    Manual(MergeFieldMap),
+}
+
+impl MergeStrategy {
+    /// Record `field` is taken from
+    pub fn side(&self, field: MergeField) -> MergeSide {
+        match self {
+            MergeStrategy::KeepTarget => MergeSide::Target,
+            MergeStrategy::KeepSource => MergeSide::Source,
+            MergeStrategy::Manual(fields) => fields.get(&field).copied().unwrap_or(MergeSide::Target),
+        }
+    }
+}
+
+/// Records moved from the source to the target of a merge
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MergeReassignments {
+    pub orders: u64,
+    pub contacts: u64,
+    pub tax_jurisdictions: u64,
+}
+
 /// Customer aggregate root implementing event sourcing
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CustomerAggregate {
@@ -367,6 +435,89 @@ impl CustomerAggregate {
         Ok(())
     }
 
+    /// Merge the duplicate `source` into `target`
+    ///
+    /// Orders, contacts and tax jurisdictions of the source move to the
+    /// target, the target takes the field values `strategy` picks and the
+    /// source is soft deleted, all in one transaction of `repository`, which
+    /// records [`CustomerEvent::Merged`] and an audit trail entry. A source
+    /// already merged into the target returns the target unchanged, so a
+    /// merge that failed part way is safe to run again.
+    pub async fn merge(
+        repository: &dyn CustomerMergeRepository,
+        source: CustomerId,
+        target: CustomerId,
+        strategy: MergeStrategy,
+        merged_by: Uuid,
+    ) -> Result<Customer> {
+        if source == target {
+            return Err(MasterDataError::ValidationError {
+                field: "source".to_string(),
+                message: "A customer cannot be merged into itself".to_string(),
+            });
+        }
+
+        repository.merge_customers(source, target, &strategy, merged_by).await
+    }
+
+    /// The event of merging `source` into `target`, carrying the values the
+    /// target ends up with
+    pub fn merge_event(
+        source: &Customer,
+        target: &Customer,
+        strategy: &MergeStrategy,
+        reassigned: MergeReassignments,
+        merged_by: Uuid,
+    ) -> Result<CustomerEvent> {
+        if source.id == target.id {
+            return Err(MasterDataError::ValidationError {
+                field: "source".to_string(),
+                message: "A customer cannot be merged into itself".to_string(),
+            });
+        }
+
+        let pick = |field| match strategy.side(field) {
+            MergeSide::Source => source,
+            MergeSide::Target => target,
+        };
+        let fields_from_source = MergeField::ALL
+            .into_iter()
+            .filter(|field| strategy.side(*field) == MergeSide::Source)
+            .collect();
+
+        Ok(CustomerEvent::Merged {
+            customer_id: target.id,
+            source_customer_id: source.id,
+            legal_name: pick(MergeField::LegalName).legal_name.clone(),
+            customer_type: pick(MergeField::CustomerType).customer_type.clone(),
+            currency_code: pick(MergeField::CurrencyCode).financial_info.currency_code.clone(),
+            credit_limit: pick(MergeField::CreditLimit).financial_info.credit_limit,
+            payment_terms: pick(MergeField::PaymentTerms).financial_info.payment_terms.clone(),
+            tax_exempt: pick(MergeField::TaxExempt).financial_info.tax_exempt,
+            fields_from_source,
+            reassigned,
+            merged_by,
+            merged_at: Utc::now(),
+        })
+    }
+
+    /// Tax jurisdictions of the target followed by those of the source in
+    /// jurisdictions the target has none for
+    pub fn merged_tax_jurisdictions(source: &Customer, target: &Customer) -> Vec<TaxJurisdiction> {
+        let mut codes: HashSet<&str> = target
+            .tax_jurisdictions
+            .iter()
+            .map(|j| j.jurisdiction_code.as_str())
+            .collect();
+        let mut merged = target.tax_jurisdictions.clone();
+        for jurisdiction in &source.tax_jurisdictions {
+            if codes.insert(jurisdiction.jurisdiction_code.as_str()) {
+                merged.push(jurisdiction.clone());
+            }
+        }
+        merged
+    }
+
     /// Get uncommitted events for persistence
     pub fn uncommitted_events(&self) -> &[CustomerEvent] {
         &self.uncommitted_events
@@ -466,6 +617,27 @@ impl CustomerAggregate {
                 self.modified_at = *restored_at;
             }
 
+            CustomerEvent::Merged {
+                legal_name,
+                customer_type,
+                currency_code,
+                credit_limit,
+                payment_terms,
+                tax_exempt,
+                merged_by,
+                merged_at,
+                ..
+            } => {
+                self.legal_name = legal_name.clone();
+                self.customer_type = customer_type.clone();
+                self.currency_code = currency_code.clone();
+                self.credit_limit = *credit_limit;
+                self.payment_terms = payment_terms.clone();
+                self.tax_exempt = *tax_exempt;
+                self.modified_by = *merged_by;
+                self.modified_at = *merged_at;
+            }
+
             // Add other event handlers as needed
             _ => {
                 // For events that don't directly modify the core aggregate state
@@ -570,6 +742,7 @@ impl CustomerAggregate {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::customer::tests::simple::sample_customer;
 
     #[test]
     fn test_create_customer_aggregate() {
@@ -693,4 +866,135 @@ mod tests {
         assert!(result.is_err());
         assert!(!aggregate.is_deleted);
     }
-}
\ No newline at end of file
+
+    fn duplicates() -> (Customer, Customer) {
+        let user_id = Uuid::new_v4();
+        let mut source = sample_customer(Uuid::new_v4(), user_id);
+        source.legal_name = "ACME Corp (CRM import)".to_string();
+        source.customer_type = CustomerType::Business;
+        source.financial_info.credit_limit = Some(rust_decimal::Decimal::from(5000));
+        let mut target = sample_customer(Uuid::new_v4(), user_id);
+        target.legal_name = "ACME Corp".to_string();
+        target.financial_info.credit_limit = Some(rust_decimal::Decimal::from(20000));
+        (source, target)
+    }
+
+    fn jurisdiction(code: &str, name: &str) -> TaxJurisdiction {
+        TaxJurisdiction {
+            jurisdiction_code: code.to_string(),
+            jurisdiction_name: name.to_string(),
+            tax_rate: None,
+            effective_date: Utc::now(),
+            expiry_date: None,
+        }
+    }
+
+    #[test]
+    fn test_merge_strategies_pick_fields() {
+        let (source, target) = duplicates();
+        let merged_by = Uuid::new_v4();
+
+        let event = CustomerAggregate::merge_event(
+            &source,
+            &target,
+            &MergeStrategy::KeepTarget,
+            MergeReassignments::default(),
+            merged_by,
+        )
+        .unwrap();
+        let CustomerEvent::Merged { customer_id, source_customer_id, legal_name, fields_from_source, .. } = event else {
+            panic!("not a merge: {:?}", event);
+        };
+        assert_eq!((customer_id, source_customer_id), (target.id, source.id));
+        assert_eq!(legal_name, "ACME Corp");
+        assert!(fields_from_source.is_empty());
+
+        let event = CustomerAggregate::merge_event(
+            &source,
+            &target,
+            &MergeStrategy::KeepSource,
+            MergeReassignments::default(),
+            merged_by,
+        )
+        .unwrap();
+        let CustomerEvent::Merged { legal_name, customer_type, fields_from_source, .. } = event else {
+            panic!("not a merge: {:?}", event);
+        };
+        assert_eq!(legal_name, "ACME Corp (CRM import)");
+        assert_eq!(customer_type, CustomerType::Business);
+        assert_eq!(fields_from_source, MergeField::ALL.to_vec());
+
+        // Fields the map leaves out keep the target's value
+        let manual = MergeStrategy::Manual(MergeFieldMap::from([
+            (MergeField::LegalName, MergeSide::Source),
+            (MergeField::CreditLimit, MergeSide::Target),
+        ]));
+        let event =
+            CustomerAggregate::merge_event(&source, &target, &manual, MergeReassignments::default(), merged_by)
+                .unwrap();
+        let CustomerEvent::Merged { legal_name, customer_type, credit_limit, fields_from_source, .. } = event else {
+            panic!("not a merge: {:?}", event);
+        };
+        assert_eq!(legal_name, "ACME Corp (CRM import)");
+        assert_eq!(customer_type, CustomerType::B2b);
+        assert_eq!(credit_limit, Some(rust_decimal::Decimal::from(20000)));
+        assert_eq!(fields_from_source, vec![MergeField::LegalName]);
+    }
+
+    #[test]
+    fn test_merge_into_itself_is_refused() {
+        let (source, _) = duplicates();
+
+        let result = CustomerAggregate::merge_event(
+            &source,
+            &source,
+            &MergeStrategy::KeepTarget,
+            MergeReassignments::default(),
+            Uuid::new_v4(),
+        );
+
+        assert!(matches!(result, Err(MasterDataError::ValidationError { .. })));
+    }
+
+    #[test]
+    fn test_merged_tax_jurisdictions_keep_the_target_per_jurisdiction() {
+        let (mut source, mut target) = duplicates();
+        source.tax_jurisdictions = vec![jurisdiction("DE", "Germany (import)"), jurisdiction("AT", "Austria")];
+        target.tax_jurisdictions = vec![jurisdiction("DE", "Germany")];
+
+        let merged = CustomerAggregate::merged_tax_jurisdictions(&source, &target);
+
+        let names: Vec<_> = merged.iter().map(|j| j.jurisdiction_name.as_str()).collect();
+        assert_eq!(names, ["Germany", "Austria"]);
+    }
+
+    #[test]
+    fn test_merge_event_updates_the_target_aggregate() {
+        let (source, target) = duplicates();
+        let mut aggregate = CustomerAggregate::create(
+            Uuid::new_v4(),
+            target.customer_number.clone(),
+            target.legal_name.clone(),
+            target.customer_type.clone(),
+            Uuid::new_v4(),
+        )
+        .unwrap();
+        let merged_by = Uuid::new_v4();
+
+        let event = CustomerAggregate::merge_event(
+            &source,
+            &target,
+            &MergeStrategy::KeepSource,
+            MergeReassignments { orders: 3, contacts: 1, tax_jurisdictions: 0 },
+            merged_by,
+        )
+        .unwrap();
+        aggregate.apply_event(&event);
+
+        assert_eq!(aggregate.legal_name, "ACME Corp (CRM import)");
+        assert_eq!(aggregate.credit_limit, Some(rust_decimal::Decimal::from(5000)));
+        assert_eq!(aggregate.modified_by, merged_by);
+        assert_eq!(event.event_type(), "customer_merged");
+        assert!(event.is_high_impact());
+    }
+}
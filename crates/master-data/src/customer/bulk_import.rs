@@ -0,0 +1,418 @@
+//! Creating many customers at once, e.g. when migrating from a legacy system
+//!
+//! [`CustomerRepository::bulk_create_customers`](crate::customer::CustomerRepository::bulk_create_customers)
+//! checks every row with [`CustomerValidator`] first and inserts the valid
+//! ones in batches of [`BulkImportOptions::batch_size`] rows, one transaction
+//! per batch. A row that fails, because it is invalid, its customer number is
+//! taken by an earlier row or an existing customer, or the database refuses
+//! it, is reported with its index in the [`BulkImportResult`] and does not
+//! stop the other rows. With [`BulkImportOptions::atomic`] all rows go into
+//! one transaction and nothing is imported unless every row is.
+//!
+//! [`CustomerService::bulk_create_customers`](crate::customer::CustomerService::bulk_create_customers)
+//! applies the business rules and hierarchy checks of a single create to each
+//! row before that, and refuses the rows the tenant's plan has no room for.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::customer::validation::CustomerValidator;
+use crate::customer::CreateCustomerRequest;
+use crate::error::MasterDataError;
+
+/// Rows per transaction unless configured otherwise
+pub const DEFAULT_BATCH_SIZE: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BulkImportOptions {
+    /// Rows inserted per transaction; a failing batch commit only loses its own rows
+    pub batch_size: usize,
+    /// Import every row or none; the batch size does not apply
+    pub atomic: bool,
+}
+
+impl Default for BulkImportOptions {
+    fn default() -> Self {
+        Self {
+            batch_size: DEFAULT_BATCH_SIZE,
+            atomic: false,
+        }
+    }
+}
+
+/// Why a row was not imported
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, thiserror::Error)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum BulkImportError {
+    #[error("Validation failed for {field}: {message}")]
+    Validation { field: String, message: String },
+    /// The customer number is used by an earlier row of the import, or by an
+    /// existing customer when `duplicate_of` is not set
+    #[error("Customer number {customer_number} is already in use")]
+    Conflict {
+        customer_number: String,
+        duplicate_of: Option<usize>,
+    },
+    #[error("The customer could not be stored: {message}")]
+    Database { message: String },
+    /// The tenant's plan has no room for this many more customers
+    #[error("The customer limit of the plan is reached")]
+    PlanLimit,
+    /// A valid row left out because another row of an atomic import failed
+    #[error("Not imported because another row failed")]
+    Aborted,
+}
+
+impl BulkImportError {
+    /// The row's error for a failed repository call; taken numbers are conflicts
+    pub fn from_error(error: MasterDataError, customer_number: Option<&str>) -> Self {
+        match error {
+            MasterDataError::ValidationError { field, message } => BulkImportError::Validation { field, message },
+            MasterDataError::DuplicateCustomerNumber { number } => BulkImportError::Conflict {
+                customer_number: number,
+                duplicate_of: None,
+            },
+            MasterDataError::Database(sqlx::Error::Database(e)) if e.is_unique_violation() && customer_number.is_some() => {
+                BulkImportError::Conflict {
+                    customer_number: customer_number.unwrap_or_default().to_string(),
+                    duplicate_of: None,
+                }
+            }
+            other => BulkImportError::Database { message: other.to_string() },
+        }
+    }
+
+    /// Errors the caller cannot fix by changing the row
+    pub fn is_internal(&self) -> bool {
+        matches!(self, BulkImportError::Database { .. })
+    }
+}
+
+/// Outcome of one row, by its index in the imported rows
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum BulkImportRow {
+    Created {
+        index: usize,
+        customer_id: Uuid,
+        customer_number: String,
+    },
+    Failed {
+        index: usize,
+        error: BulkImportError,
+    },
+}
+
+impl BulkImportRow {
+    pub fn index(&self) -> usize {
+        match self {
+            BulkImportRow::Created { index, .. } | BulkImportRow::Failed { index, .. } => *index,
+        }
+    }
+
+    pub fn is_created(&self) -> bool {
+        matches!(self, BulkImportRow::Created { .. })
+    }
+
+    /// The row of an import of some of the rows, numbered by the original row
+    /// index of each, `indices[index]`
+    pub fn renumbered(self, indices: &[usize]) -> Self {
+        match self {
+            BulkImportRow::Created { index, customer_id, customer_number } => BulkImportRow::Created {
+                index: indices[index],
+                customer_id,
+                customer_number,
+            },
+            BulkImportRow::Failed { index, error } => BulkImportRow::Failed {
+                index: indices[index],
+                error: match error {
+                    BulkImportError::Conflict { customer_number, duplicate_of } => BulkImportError::Conflict {
+                        customer_number,
+                        duplicate_of: duplicate_of.map(|first| indices[first]),
+                    },
+                    other => other,
+                },
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BulkImportResult {
+    /// One entry per imported row, in row order
+    pub rows: Vec<BulkImportRow>,
+    /// An atomic import that failed; none of the rows were stored
+    pub aborted: bool,
+}
+
+impl BulkImportResult {
+    pub fn new(mut rows: Vec<BulkImportRow>) -> Self {
+        rows.sort_by_key(BulkImportRow::index);
+        Self { rows, aborted: false }
+    }
+
+    /// The result of an atomic import that failed: rows that went in are
+    /// rolled back with the rest
+    pub fn aborted(rows: Vec<BulkImportRow>) -> Self {
+        let rows = rows
+            .into_iter()
+            .map(|row| match row {
+                BulkImportRow::Created { index, .. } => BulkImportRow::Failed {
+                    index,
+                    error: BulkImportError::Aborted,
+                },
+                failed => failed,
+            })
+            .collect();
+        Self {
+            aborted: true,
+            ..Self::new(rows)
+        }
+    }
+
+    pub fn created(&self) -> usize {
+        self.rows.iter().filter(|row| row.is_created()).count()
+    }
+
+    pub fn failed(&self) -> usize {
+        self.rows.len() - self.created()
+    }
+
+    pub fn is_clean(&self) -> bool {
+        self.failed() == 0
+    }
+}
+
+/// The rows to insert, in batches, and those refused before touching the database
+#[derive(Debug)]
+pub struct BulkImportPlan {
+    pub rejected: Vec<BulkImportRow>,
+    pub batches: Vec<Vec<(usize, CreateCustomerRequest)>>,
+}
+
+impl BulkImportPlan {
+    pub fn new(requests: Vec<CreateCustomerRequest>, options: &BulkImportOptions) -> Self {
+        let validator = CustomerValidator::new();
+        let mut conflicts = in_batch_conflicts(&requests);
+        let mut rejected = Vec::new();
+        let mut accepted = Vec::new();
+        for (index, request) in requests.into_iter().enumerate() {
+            match validate_row(&validator, &request).err().or_else(|| conflicts.remove(&index)) {
+                Some(error) => rejected.push(BulkImportRow::Failed { index, error }),
+                None => accepted.push((index, request)),
+            }
+        }
+
+        let batch_size = if options.atomic { accepted.len() } else { options.batch_size };
+        let mut batches = Vec::new();
+        let mut rows = accepted.into_iter().peekable();
+        while rows.peek().is_some() {
+            batches.push(rows.by_ref().take(batch_size.max(1)).collect());
+        }
+        Self { rejected, batches }
+    }
+
+    /// Rows that would be inserted
+    pub fn accepted(&self) -> usize {
+        self.batches.iter().map(Vec::len).sum()
+    }
+}
+
+/// The checks of a single create, without the database
+pub fn validate_row(validator: &CustomerValidator, request: &CreateCustomerRequest) -> Result<(), BulkImportError> {
+    let error = |e: MasterDataError| BulkImportError::from_error(e, None);
+
+    request.validate().map_err(|e| BulkImportError::Validation {
+        field: "request".to_string(),
+        message: e.to_string(),
+    })?;
+    validator.validate_legal_name(&request.legal_name).map_err(error)?;
+    if let Some(customer_number) = &request.customer_number {
+        validator.validate_customer_number(customer_number).map_err(error)?;
+    }
+    for (i, address) in request.addresses.iter().flatten().enumerate() {
+        validator
+            .validate_address(&format!("addresses[{}]", i), &address.postal_address())
+            .map_err(error)?;
+    }
+    for contact in request.contacts.iter().flatten() {
+        if let Some(email) = &contact.email {
+            validator.validate_email(email).map_err(error)?;
+        }
+        if let Some(phone) = &contact.phone {
+            validator.validate_phone(phone).map_err(error)?;
+        }
+    }
+    Ok(())
+}
+
+/// Rows whose customer number an earlier row of the same import already uses
+pub fn in_batch_conflicts(requests: &[CreateCustomerRequest]) -> HashMap<usize, BulkImportError> {
+    let mut first_use: HashMap<&str, usize> = HashMap::new();
+    let mut conflicts = HashMap::new();
+    for (index, request) in requests.iter().enumerate() {
+        let Some(customer_number) = request.customer_number.as_deref() else {
+            continue;
+        };
+        match first_use.get(customer_number) {
+            Some(&first) => {
+                conflicts.insert(
+                    index,
+                    BulkImportError::Conflict {
+                        customer_number: customer_number.to_string(),
+                        duplicate_of: Some(first),
+                    },
+                );
+            }
+            None => {
+                first_use.insert(customer_number, index);
+            }
+        }
+    }
+    conflicts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::customer::model::CreateContactRequest;
+    use crate::customer::CustomerType;
+    use crate::types::ContactType;
+
+    fn request(customer_number: Option<&str>, legal_name: &str) -> CreateCustomerRequest {
+        serde_json::from_value(serde_json::json!({
+            "customer_number": customer_number,
+            "legal_name": legal_name,
+            "customer_type": CustomerType::B2b,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_plan_reports_invalid_rows_and_duplicates_and_batches_the_rest() {
+        let mut bad_email = request(Some("C-5"), "Email GmbH");
+        bad_email.contacts = Some(vec![CreateContactRequest {
+            contact_type: ContactType::Primary,
+            first_name: "Ada".to_string(),
+            last_name: "Lovelace".to_string(),
+            title: None,
+            department: None,
+            email: Some("not-an-email".to_string()),
+            phone: None,
+            mobile: None,
+            preferred_language: None,
+            communication_preferences: None,
+            is_primary: None,
+        }]);
+        let requests = vec![
+            request(Some("C-1"), "Acme GmbH"),
+            request(None, "Generated AG"),
+            request(Some("C-1"), "Acme Copy GmbH"),
+            request(Some("c 3"), "Lowercase Ltd"),
+            request(Some("C-4"), " "),
+            bad_email,
+            request(Some("C-6"), "Six SE"),
+            request(None, "Seven KG"),
+        ];
+
+        let plan = BulkImportPlan::new(requests, &BulkImportOptions { batch_size: 2, atomic: false });
+
+        let rejected: HashMap<usize, &BulkImportError> = plan
+            .rejected
+            .iter()
+            .map(|row| match row {
+                BulkImportRow::Failed { index, error } => (*index, error),
+                created => panic!("not rejected: {:?}", created),
+            })
+            .collect();
+        assert_eq!(rejected.len(), 4);
+        assert_eq!(
+            rejected[&2],
+            &BulkImportError::Conflict { customer_number: "C-1".to_string(), duplicate_of: Some(0) }
+        );
+        assert!(matches!(rejected[&3], BulkImportError::Validation { field, .. } if field == "customer_number"));
+        assert!(matches!(rejected[&4], BulkImportError::Validation { .. }));
+        assert!(matches!(rejected[&5], BulkImportError::Validation { .. }));
+
+        let batches: Vec<Vec<usize>> =
+            plan.batches.iter().map(|batch| batch.iter().map(|(index, _)| *index).collect()).collect();
+        assert_eq!(batches, vec![vec![0, 1], vec![6, 7]]);
+        assert_eq!(plan.accepted(), 4);
+    }
+
+    #[test]
+    fn test_atomic_plan_is_a_single_batch() {
+        let requests = (0..5).map(|i| request(Some(&format!("C-{}", i)), "Acme GmbH")).collect();
+        let plan = BulkImportPlan::new(requests, &BulkImportOptions { batch_size: 2, atomic: true });
+        assert!(plan.rejected.is_empty());
+        assert_eq!(plan.batches.len(), 1);
+        assert_eq!(plan.batches[0].len(), 5);
+
+        let empty = BulkImportPlan::new(Vec::new(), &BulkImportOptions { batch_size: 0, atomic: false });
+        assert!(empty.batches.is_empty());
+    }
+
+    #[test]
+    fn test_aborted_import_rolls_back_created_rows() {
+        let customer_id = Uuid::new_v4();
+        let rows = vec![
+            BulkImportRow::Failed {
+                index: 1,
+                error: BulkImportError::Conflict { customer_number: "C-1".to_string(), duplicate_of: None },
+            },
+            BulkImportRow::Created { index: 0, customer_id, customer_number: "C-0".to_string() },
+        ];
+
+        let result = BulkImportResult::new(rows.clone());
+        assert_eq!((result.created(), result.failed(), result.aborted), (1, 1, false));
+        assert_eq!(result.rows[0].index(), 0);
+
+        let aborted = BulkImportResult::aborted(rows);
+        assert!(aborted.aborted);
+        assert_eq!(aborted.created(), 0);
+        assert_eq!(aborted.rows[0], BulkImportRow::Failed { index: 0, error: BulkImportError::Aborted });
+        assert!(!aborted.is_clean());
+    }
+
+    #[test]
+    fn test_renumbered_rows_point_at_the_original_rows() {
+        let customer_id = Uuid::new_v4();
+        let indices = [1, 4, 6];
+        let created = BulkImportRow::Created { index: 0, customer_id, customer_number: "C-1".to_string() };
+        let duplicate = BulkImportRow::Failed {
+            index: 2,
+            error: BulkImportError::Conflict { customer_number: "C-1".to_string(), duplicate_of: Some(0) },
+        };
+
+        assert_eq!(
+            created.renumbered(&indices),
+            BulkImportRow::Created { index: 1, customer_id, customer_number: "C-1".to_string() }
+        );
+        assert_eq!(
+            duplicate.renumbered(&indices),
+            BulkImportRow::Failed {
+                index: 6,
+                error: BulkImportError::Conflict { customer_number: "C-1".to_string(), duplicate_of: Some(1) },
+            }
+        );
+    }
+
+    #[test]
+    fn test_row_results_serialize_with_status_and_error_kind() {
+        let row = BulkImportRow::Failed {
+            index: 3,
+            error: BulkImportError::Conflict { customer_number: "C-1".to_string(), duplicate_of: Some(0) },
+        };
+        assert_eq!(
+            serde_json::to_value(&row).unwrap(),
+            serde_json::json!({
+                "status": "failed",
+                "index": 3,
+                "error": { "kind": "conflict", "customer_number": "C-1", "duplicate_of": 0 }
+            })
+        );
+    }
+}
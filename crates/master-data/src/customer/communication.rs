@@ -0,0 +1,529 @@
+//! # Customer Communication Log
+//!
+//! Emails, calls, meetings and notes recorded against a customer, each with an
+//! optional follow-up date. Entries are logged by users or generated by the
+//! system when it emails a customer (`logged_by` is then the sending user, if any).
+//!
+//! ## Scoping
+//!
+//! Within a tenant, `customers:read` lets a user see their own entries and
+//! system-generated ones; `communications:manage` extends that to everybody's
+//! entries. Logging needs `customers:write`, and only the author (or a holder
+//! of `communications:manage`) may change or delete an entry.
+//!
+//! ## Follow-ups
+//!
+//! [`classify_follow_ups`] splits a user's open follow-ups into overdue and
+//! upcoming ones; [`follow_up_digests`] groups them by owner for the daily
+//! reminder.
+
+use crate::error::{MasterDataError, Result};
+use crate::types::TenantContext;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+pub const PERMISSION_READ: &str = "customers:read";
+pub const PERMISSION_WRITE: &str = "customers:write";
+pub const PERMISSION_MANAGE: &str = "communications:manage";
+
+const MAX_SUBJECT_LENGTH: usize = 255;
+
+/// Kind of interaction with a customer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CommunicationType {
+    Email,
+    Call,
+    Meeting,
+    Note,
+}
+
+impl CommunicationType {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CommunicationType::Email => "email",
+            CommunicationType::Call => "call",
+            CommunicationType::Meeting => "meeting",
+            CommunicationType::Note => "note",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "email" => Some(CommunicationType::Email),
+            "call" => Some(CommunicationType::Call),
+            "meeting" => Some(CommunicationType::Meeting),
+            "note" => Some(CommunicationType::Note),
+            _ => None,
+        }
+    }
+}
+
+/// Who initiated the interaction; notes are `Internal`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CommunicationDirection {
+    Inbound,
+    Outbound,
+    Internal,
+}
+
+impl CommunicationDirection {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CommunicationDirection::Inbound => "inbound",
+            CommunicationDirection::Outbound => "outbound",
+            CommunicationDirection::Internal => "internal",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "inbound" => Some(CommunicationDirection::Inbound),
+            "outbound" => Some(CommunicationDirection::Outbound),
+            "internal" => Some(CommunicationDirection::Internal),
+            _ => None,
+        }
+    }
+}
+
+/// One logged interaction with a customer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CustomerCommunication {
+    pub id: Uuid,
+    pub tenant_id: Uuid,
+    pub customer_id: Uuid,
+    pub communication_type: CommunicationType,
+    pub direction: CommunicationDirection,
+    pub subject: String,
+    pub body: Option<String>,
+    pub occurred_at: DateTime<Utc>,
+    /// Author of the entry; `None` for system emails not sent on behalf of a user
+    pub logged_by: Option<Uuid>,
+    pub is_system_generated: bool,
+    pub follow_up_due: Option<DateTime<Utc>>,
+    pub follow_up_done: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateCommunicationRequest {
+    pub communication_type: CommunicationType,
+    pub direction: CommunicationDirection,
+    pub subject: String,
+    pub body: Option<String>,
+    /// Defaults to now
+    pub occurred_at: Option<DateTime<Utc>>,
+    pub follow_up_due: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdateCommunicationRequest {
+    pub subject: Option<String>,
+    pub body: Option<String>,
+    pub occurred_at: Option<DateTime<Utc>>,
+    pub follow_up_due: Option<DateTime<Utc>>,
+    /// Removes the follow-up altogether
+    #[serde(default)]
+    pub clear_follow_up: bool,
+    pub follow_up_done: Option<bool>,
+}
+
+impl CustomerCommunication {
+    /// Build a user-logged entry
+    pub fn log(
+        tenant_id: Uuid,
+        customer_id: Uuid,
+        request: CreateCommunicationRequest,
+        logged_by: Uuid,
+        now: DateTime<Utc>,
+    ) -> Result<Self> {
+        let subject = validate_subject(&request.subject)?;
+
+        Ok(Self {
+            id: Uuid::new_v4(),
+            tenant_id,
+            customer_id,
+            communication_type: request.communication_type,
+            direction: request.direction,
+            subject,
+            body: request.body.filter(|b| !b.trim().is_empty()),
+            occurred_at: request.occurred_at.unwrap_or(now),
+            logged_by: Some(logged_by),
+            is_system_generated: false,
+            follow_up_due: request.follow_up_due,
+            follow_up_done: false,
+            created_at: now,
+            updated_at: now,
+        })
+    }
+
+    /// Build the entry recorded when the system emails a customer
+    pub fn system_email(
+        tenant_id: Uuid,
+        customer_id: Uuid,
+        to: &str,
+        subject: &str,
+        body: Option<&str>,
+        sent_by: Option<Uuid>,
+        sent_at: DateTime<Utc>,
+    ) -> Self {
+        let subject: String = if subject.trim().is_empty() {
+            format!("Email to {}", to)
+        } else {
+            subject.trim().chars().take(MAX_SUBJECT_LENGTH).collect()
+        };
+
+        Self {
+            id: Uuid::new_v4(),
+            tenant_id,
+            customer_id,
+            communication_type: CommunicationType::Email,
+            direction: CommunicationDirection::Outbound,
+            subject,
+            body: body.map(str::to_string),
+            occurred_at: sent_at,
+            logged_by: sent_by,
+            is_system_generated: true,
+            follow_up_due: None,
+            follow_up_done: false,
+            created_at: sent_at,
+            updated_at: sent_at,
+        }
+    }
+
+    /// Apply an update. System-generated entries only accept follow-up changes.
+    pub fn apply_update(&mut self, update: UpdateCommunicationRequest, now: DateTime<Utc>) -> Result<()> {
+        if self.is_system_generated
+            && (update.subject.is_some() || update.body.is_some() || update.occurred_at.is_some())
+        {
+            return Err(MasterDataError::ValidationError {
+                field: "communication".to_string(),
+                message: "System-generated entries only allow follow-up changes".to_string(),
+            });
+        }
+
+        if let Some(subject) = update.subject {
+            self.subject = validate_subject(&subject)?;
+        }
+        if let Some(body) = update.body {
+            self.body = Some(body).filter(|b| !b.trim().is_empty());
+        }
+        if let Some(occurred_at) = update.occurred_at {
+            self.occurred_at = occurred_at;
+        }
+
+        if update.clear_follow_up {
+            self.follow_up_due = None;
+            self.follow_up_done = false;
+        } else if let Some(due) = update.follow_up_due {
+            // A new date reopens a completed follow-up
+            self.follow_up_due = Some(due);
+            self.follow_up_done = false;
+        }
+
+        if let Some(done) = update.follow_up_done {
+            if done && self.follow_up_due.is_none() {
+                return Err(MasterDataError::ValidationError {
+                    field: "follow_up_done".to_string(),
+                    message: "Entry has no follow-up to complete".to_string(),
+                });
+            }
+            self.follow_up_done = done;
+        }
+
+        self.updated_at = now;
+        Ok(())
+    }
+
+    /// Open follow-up date, if any
+    pub fn open_follow_up(&self) -> Option<DateTime<Utc>> {
+        self.follow_up_due.filter(|_| !self.follow_up_done)
+    }
+}
+
+fn validate_subject(subject: &str) -> Result<String> {
+    let subject = subject.trim();
+    if subject.is_empty() || subject.chars().count() > MAX_SUBJECT_LENGTH {
+        return Err(MasterDataError::ValidationError {
+            field: "subject".to_string(),
+            message: format!("Subject is required and at most {} characters", MAX_SUBJECT_LENGTH),
+        });
+    }
+    Ok(subject.to_string())
+}
+
+/// What the acting user may see and change
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommunicationScope {
+    pub user_id: Uuid,
+    pub can_read: bool,
+    pub can_write: bool,
+    pub can_manage: bool,
+}
+
+impl CommunicationScope {
+    pub fn for_context(context: &TenantContext) -> Self {
+        let can_manage = context.has_permission(PERMISSION_MANAGE);
+        Self {
+            user_id: context.user_id,
+            can_read: can_manage || context.has_permission(PERMISSION_READ),
+            can_write: can_manage || context.has_permission(PERMISSION_WRITE),
+            can_manage,
+        }
+    }
+
+    /// Author filter for listings; `None` means every entry is visible
+    pub fn visible_author(&self) -> Option<Uuid> {
+        (!self.can_manage).then_some(self.user_id)
+    }
+
+    pub fn can_view(&self, entry: &CustomerCommunication) -> bool {
+        self.can_read
+            && (self.can_manage || entry.is_system_generated || entry.logged_by == Some(self.user_id))
+    }
+
+    pub fn can_modify(&self, entry: &CustomerCommunication) -> bool {
+        self.can_write && (self.can_manage || entry.logged_by == Some(self.user_id))
+    }
+}
+
+/// Open follow-ups split around `now`
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FollowUpsDue {
+    pub overdue: Vec<CustomerCommunication>,
+    pub upcoming: Vec<CustomerCommunication>,
+}
+
+impl FollowUpsDue {
+    pub fn len(&self) -> usize {
+        self.overdue.len() + self.upcoming.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Split open follow-ups into overdue (due before `now`) and upcoming (due
+/// within `horizon`), oldest first. Completed and later follow-ups are dropped.
+pub fn classify_follow_ups(
+    entries: Vec<CustomerCommunication>,
+    now: DateTime<Utc>,
+    horizon: Duration,
+) -> FollowUpsDue {
+    let until = now + horizon;
+    let mut due = FollowUpsDue::default();
+
+    for entry in entries {
+        match entry.open_follow_up() {
+            Some(at) if at < now => due.overdue.push(entry),
+            Some(at) if at <= until => due.upcoming.push(entry),
+            _ => {}
+        }
+    }
+
+    due.overdue.sort_by_key(|e| e.follow_up_due);
+    due.upcoming.sort_by_key(|e| e.follow_up_due);
+    due
+}
+
+/// One user's daily reminder
+#[derive(Debug, Clone, Serialize)]
+pub struct FollowUpDigest {
+    pub tenant_id: Uuid,
+    pub user_id: Uuid,
+    pub due: FollowUpsDue,
+}
+
+/// Group open follow-ups by owner for the daily reminder. Entries without an
+/// owner and owners with nothing due are skipped.
+pub fn follow_up_digests(
+    entries: Vec<CustomerCommunication>,
+    now: DateTime<Utc>,
+    horizon: Duration,
+) -> Vec<FollowUpDigest> {
+    let mut by_owner: BTreeMap<(Uuid, Uuid), Vec<CustomerCommunication>> = BTreeMap::new();
+    for entry in entries {
+        if let Some(owner) = entry.logged_by {
+            by_owner.entry((entry.tenant_id, owner)).or_default().push(entry);
+        }
+    }
+
+    by_owner
+        .into_iter()
+        .map(|((tenant_id, user_id), entries)| FollowUpDigest {
+            tenant_id,
+            user_id,
+            due: classify_follow_ups(entries, now, horizon),
+        })
+        .filter(|digest| !digest.due.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(subject: &str, follow_up_due: Option<DateTime<Utc>>) -> CreateCommunicationRequest {
+        CreateCommunicationRequest {
+            communication_type: CommunicationType::Call,
+            direction: CommunicationDirection::Outbound,
+            subject: subject.to_string(),
+            body: None,
+            occurred_at: None,
+            follow_up_due,
+        }
+    }
+
+    fn context(user_id: Uuid, permissions: &[&str]) -> TenantContext {
+        let mut context = TenantContext::new(Uuid::new_v4(), "tenant".to_string(), user_id);
+        context.permissions = permissions.iter().map(|p| p.to_string()).collect();
+        context
+    }
+
+    #[test]
+    fn test_log_and_update_entry() {
+        let now = Utc::now();
+        let (tenant, customer, user) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+
+        assert!(CustomerCommunication::log(tenant, customer, request("  ", None), user, now).is_err());
+
+        let mut entry = CustomerCommunication::log(tenant, customer, request(" Pricing call ", None), user, now).unwrap();
+        assert_eq!(entry.subject, "Pricing call");
+        assert_eq!(entry.occurred_at, now);
+        assert_eq!(entry.logged_by, Some(user));
+        assert!(!entry.is_system_generated);
+
+        // Completing a follow-up that doesn't exist is rejected
+        let done = UpdateCommunicationRequest { follow_up_done: Some(true), ..Default::default() };
+        assert!(entry.apply_update(done.clone(), now).is_err());
+
+        let due = now + Duration::days(2);
+        entry.apply_update(UpdateCommunicationRequest { follow_up_due: Some(due), ..Default::default() }, now).unwrap();
+        assert_eq!(entry.open_follow_up(), Some(due));
+
+        entry.apply_update(done, now).unwrap();
+        assert_eq!(entry.open_follow_up(), None);
+
+        entry.apply_update(UpdateCommunicationRequest { clear_follow_up: true, ..Default::default() }, now).unwrap();
+        assert_eq!(entry.follow_up_due, None);
+    }
+
+    #[test]
+    fn test_scoping() {
+        let now = Utc::now();
+        let (tenant, customer) = (Uuid::new_v4(), Uuid::new_v4());
+        let (author, colleague, manager) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+
+        let entry = CustomerCommunication::log(tenant, customer, request("Visit", None), author, now).unwrap();
+        let system = CustomerCommunication::system_email(tenant, customer, "a@b.com", "Invoice", None, None, now);
+
+        let own = CommunicationScope::for_context(&context(author, &[PERMISSION_READ, PERMISSION_WRITE]));
+        assert!(own.can_view(&entry) && own.can_modify(&entry));
+        assert!(own.can_view(&system) && !own.can_modify(&system));
+        assert_eq!(own.visible_author(), Some(author));
+
+        let other = CommunicationScope::for_context(&context(colleague, &[PERMISSION_READ, PERMISSION_WRITE]));
+        assert!(!other.can_view(&entry) && !other.can_modify(&entry));
+
+        let reader = CommunicationScope::for_context(&context(author, &[PERMISSION_READ]));
+        assert!(reader.can_view(&entry) && !reader.can_modify(&entry));
+
+        let nobody = CommunicationScope::for_context(&context(author, &[]));
+        assert!(!nobody.can_view(&entry) && !nobody.can_view(&system));
+
+        let admin = CommunicationScope::for_context(&context(manager, &[PERMISSION_MANAGE]));
+        assert!(admin.can_view(&entry) && admin.can_modify(&entry) && admin.can_modify(&system));
+        assert_eq!(admin.visible_author(), None);
+    }
+
+    #[test]
+    fn test_system_email_entries() {
+        let now = Utc::now();
+        let (tenant, customer, sender) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+
+        let mut entry = CustomerCommunication::system_email(
+            tenant, customer, "buyer@example.com", "Order confirmation", Some("Thanks"), Some(sender), now,
+        );
+        assert!(entry.is_system_generated);
+        assert_eq!(entry.communication_type, CommunicationType::Email);
+        assert_eq!(entry.direction, CommunicationDirection::Outbound);
+        assert_eq!(entry.logged_by, Some(sender));
+
+        let untitled = CustomerCommunication::system_email(tenant, customer, "buyer@example.com", "", None, None, now);
+        assert_eq!(untitled.subject, "Email to buyer@example.com");
+
+        // Content is immutable, follow-ups are not
+        let edit = UpdateCommunicationRequest { subject: Some("Changed".to_string()), ..Default::default() };
+        assert!(entry.apply_update(edit, now).is_err());
+        let due = now + Duration::days(1);
+        entry.apply_update(UpdateCommunicationRequest { follow_up_due: Some(due), ..Default::default() }, now).unwrap();
+        assert_eq!(entry.open_follow_up(), Some(due));
+    }
+
+    #[test]
+    fn test_follow_ups_due_spans_overdue_and_upcoming() {
+        let now = Utc::now();
+        let (tenant, customer, user) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let at = |days: i64, subject: &str| {
+            CustomerCommunication::log(tenant, customer, request(subject, Some(now + Duration::days(days))), user, now).unwrap()
+        };
+
+        let mut done = at(-1, "done");
+        done.follow_up_done = true;
+        let entries = vec![
+            at(3, "in three days"),
+            at(-5, "five days late"),
+            at(30, "next month"),
+            done,
+            at(-1, "one day late"),
+            at(1, "tomorrow"),
+            CustomerCommunication::log(tenant, customer, request("no follow-up", None), user, now).unwrap(),
+        ];
+
+        let due = classify_follow_ups(entries, now, Duration::days(7));
+        let subjects = |list: &[CustomerCommunication]| list.iter().map(|e| e.subject.clone()).collect::<Vec<_>>();
+        assert_eq!(subjects(&due.overdue), vec!["five days late", "one day late"]);
+        assert_eq!(subjects(&due.upcoming), vec!["tomorrow", "in three days"]);
+        assert_eq!(due.len(), 4);
+    }
+
+    #[test]
+    fn test_follow_up_digests_group_by_owner() {
+        let now = Utc::now();
+        let (tenant, other_tenant, customer) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let (alice, bob) = (Uuid::new_v4(), Uuid::new_v4());
+        let entry = |tenant_id: Uuid, owner: Uuid, days: i64| {
+            CustomerCommunication::log(tenant_id, customer, request("Call back", Some(now + Duration::days(days))), owner, now)
+                .unwrap()
+        };
+
+        let mut orphan = CustomerCommunication::system_email(tenant, customer, "a@b.com", "Invoice", None, None, now);
+        orphan.follow_up_due = Some(now - Duration::days(1));
+
+        let digests = follow_up_digests(
+            vec![
+                entry(tenant, alice, -3),
+                entry(tenant, alice, 0),
+                entry(tenant, bob, 20),
+                entry(other_tenant, alice, -1),
+                orphan,
+            ],
+            now,
+            Duration::days(1),
+        );
+
+        // Bob has nothing due yet; the system entry has no owner to remind
+        assert_eq!(digests.len(), 2);
+        let alice_here = digests.iter().find(|d| d.tenant_id == tenant).unwrap();
+        assert_eq!(alice_here.user_id, alice);
+        assert_eq!(alice_here.due.overdue.len(), 1);
+        assert_eq!(alice_here.due.upcoming.len(), 1);
+        let alice_elsewhere = digests.iter().find(|d| d.tenant_id == other_tenant).unwrap();
+        assert_eq!(alice_elsewhere.due.len(), 1);
+    }
+}
@@ -0,0 +1,390 @@
+use async_trait::async_trait;
+use chrono::{Duration, Utc};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::customer::communication::*;
+use crate::customer::repository::CommunicationRepository;
+use crate::error::{MasterDataError, Result};
+use crate::types::TenantContext;
+
+/// Customer communication log with per-user scoping
+#[async_trait]
+pub trait CommunicationService: Send + Sync {
+    /// Log an email, call, meeting or note against a customer
+    async fn log_communication(&self, customer_id: Uuid, request: CreateCommunicationRequest) -> Result<CustomerCommunication>;
+
+    async fn get_communication(&self, customer_id: Uuid, id: Uuid) -> Result<CustomerCommunication>;
+
+    /// Entries of a customer visible to the acting user, newest first
+    async fn list_communications(&self, customer_id: Uuid, limit: u32, offset: u32) -> Result<Vec<CustomerCommunication>>;
+
+    async fn update_communication(&self, customer_id: Uuid, id: Uuid, request: UpdateCommunicationRequest) -> Result<CustomerCommunication>;
+
+    async fn delete_communication(&self, customer_id: Uuid, id: Uuid) -> Result<()>;
+
+    /// The acting user's open follow-ups: all overdue ones and those due within `horizon_days`
+    async fn my_follow_ups(&self, horizon_days: u32) -> Result<FollowUpsDue>;
+}
+
+pub struct DefaultCommunicationService {
+    repository: Arc<dyn CommunicationRepository>,
+    tenant_context: TenantContext,
+}
+
+impl DefaultCommunicationService {
+    pub fn new(repository: Arc<dyn CommunicationRepository>, tenant_context: TenantContext) -> Self {
+        Self {
+            repository,
+            tenant_context,
+        }
+    }
+
+    fn scope(&self) -> CommunicationScope {
+        CommunicationScope::for_context(&self.tenant_context)
+    }
+
+    fn denied(action: &str) -> MasterDataError {
+        MasterDataError::PermissionDenied { action: action.to_string() }
+    }
+
+    async fn ensure_customer(&self, customer_id: Uuid) -> Result<()> {
+        if !self.repository.customer_exists(self.tenant_context.tenant_id, customer_id).await? {
+            return Err(MasterDataError::CustomerNotFound { id: customer_id.to_string() });
+        }
+        Ok(())
+    }
+
+    /// Load an entry of the customer; entries the user may not see are reported as missing
+    async fn load_visible(&self, customer_id: Uuid, id: Uuid) -> Result<CustomerCommunication> {
+        self.repository
+            .get_communication(self.tenant_context.tenant_id, id)
+            .await?
+            .filter(|entry| entry.customer_id == customer_id && self.scope().can_view(entry))
+            .ok_or_else(|| MasterDataError::CommunicationNotFound { id: id.to_string() })
+    }
+}
+
+#[async_trait]
+impl CommunicationService for DefaultCommunicationService {
+    async fn log_communication(&self, customer_id: Uuid, request: CreateCommunicationRequest) -> Result<CustomerCommunication> {
+        if !self.scope().can_write {
+            return Err(Self::denied("log customer communication"));
+        }
+        self.ensure_customer(customer_id).await?;
+
+        let entry = CustomerCommunication::log(
+            self.tenant_context.tenant_id,
+            customer_id,
+            request,
+            self.tenant_context.user_id,
+            Utc::now(),
+        )?;
+        self.repository.insert_communication(&entry).await?;
+        Ok(entry)
+    }
+
+    async fn get_communication(&self, customer_id: Uuid, id: Uuid) -> Result<CustomerCommunication> {
+        if !self.scope().can_read {
+            return Err(Self::denied("view customer communications"));
+        }
+        self.load_visible(customer_id, id).await
+    }
+
+    async fn list_communications(&self, customer_id: Uuid, limit: u32, offset: u32) -> Result<Vec<CustomerCommunication>> {
+        let scope = self.scope();
+        if !scope.can_read {
+            return Err(Self::denied("view customer communications"));
+        }
+        self.ensure_customer(customer_id).await?;
+
+        self.repository
+            .list_communications(
+                self.tenant_context.tenant_id,
+                customer_id,
+                scope.visible_author(),
+                limit.clamp(1, 200) as i64,
+                offset as i64,
+            )
+            .await
+    }
+
+    async fn update_communication(&self, customer_id: Uuid, id: Uuid, request: UpdateCommunicationRequest) -> Result<CustomerCommunication> {
+        let mut entry = self.load_visible(customer_id, id).await?;
+        if !self.scope().can_modify(&entry) {
+            return Err(Self::denied("update customer communication"));
+        }
+
+        entry.apply_update(request, Utc::now())?;
+        self.repository.update_communication(&entry).await?;
+        Ok(entry)
+    }
+
+    async fn delete_communication(&self, customer_id: Uuid, id: Uuid) -> Result<()> {
+        let entry = self.load_visible(customer_id, id).await?;
+        let scope = self.scope();
+        // System-generated entries are part of the audit trail of what was sent
+        if !scope.can_modify(&entry) || (entry.is_system_generated && !scope.can_manage) {
+            return Err(Self::denied("delete customer communication"));
+        }
+
+        if !self.repository.delete_communication(self.tenant_context.tenant_id, id).await? {
+            return Err(MasterDataError::CommunicationNotFound { id: id.to_string() });
+        }
+        Ok(())
+    }
+
+    async fn my_follow_ups(&self, horizon_days: u32) -> Result<FollowUpsDue> {
+        if !self.scope().can_read {
+            return Err(Self::denied("view customer communications"));
+        }
+
+        let now = Utc::now();
+        let horizon = Duration::days(horizon_days.min(365) as i64);
+        let entries = self
+            .repository
+            .open_follow_ups_for_user(self.tenant_context.tenant_id, self.tenant_context.user_id, now + horizon)
+            .await?;
+
+        Ok(classify_follow_ups(entries, now, horizon))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{DateTime, Utc};
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct MemoryRepository {
+        customers: Vec<(Uuid, Uuid)>,
+        entries: Mutex<Vec<CustomerCommunication>>,
+    }
+
+    #[async_trait]
+    impl CommunicationRepository for MemoryRepository {
+        async fn customer_exists(&self, tenant_id: Uuid, customer_id: Uuid) -> Result<bool> {
+            Ok(self.customers.contains(&(tenant_id, customer_id)))
+        }
+
+        async fn insert_communication(&self, entry: &CustomerCommunication) -> Result<()> {
+            self.entries.lock().unwrap().push(entry.clone());
+            Ok(())
+        }
+
+        async fn get_communication(&self, tenant_id: Uuid, id: Uuid) -> Result<Option<CustomerCommunication>> {
+            Ok(self.entries.lock().unwrap().iter().find(|e| e.tenant_id == tenant_id && e.id == id).cloned())
+        }
+
+        async fn list_communications(
+            &self,
+            tenant_id: Uuid,
+            customer_id: Uuid,
+            visible_author: Option<Uuid>,
+            limit: i64,
+            offset: i64,
+        ) -> Result<Vec<CustomerCommunication>> {
+            let mut entries: Vec<_> = self
+                .entries
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|e| e.tenant_id == tenant_id && e.customer_id == customer_id)
+                .filter(|e| visible_author.is_none_or(|a| e.logged_by == Some(a) || e.is_system_generated))
+                .cloned()
+                .collect();
+            entries.sort_by_key(|e| std::cmp::Reverse(e.occurred_at));
+            Ok(entries.into_iter().skip(offset as usize).take(limit as usize).collect())
+        }
+
+        async fn update_communication(&self, entry: &CustomerCommunication) -> Result<()> {
+            let mut entries = self.entries.lock().unwrap();
+            let slot = entries.iter_mut().find(|e| e.id == entry.id).unwrap();
+            *slot = entry.clone();
+            Ok(())
+        }
+
+        async fn delete_communication(&self, tenant_id: Uuid, id: Uuid) -> Result<bool> {
+            let mut entries = self.entries.lock().unwrap();
+            let before = entries.len();
+            entries.retain(|e| !(e.tenant_id == tenant_id && e.id == id));
+            Ok(entries.len() < before)
+        }
+
+        async fn open_follow_ups_for_user(
+            &self,
+            tenant_id: Uuid,
+            user_id: Uuid,
+            due_before: DateTime<Utc>,
+        ) -> Result<Vec<CustomerCommunication>> {
+            Ok(self
+                .entries
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|e| e.tenant_id == tenant_id && e.logged_by == Some(user_id))
+                .filter(|e| e.open_follow_up().is_some_and(|due| due <= due_before))
+                .cloned()
+                .collect())
+        }
+
+        async fn open_follow_ups_due(&self, due_before: DateTime<Utc>) -> Result<Vec<CustomerCommunication>> {
+            Ok(self
+                .entries
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|e| e.open_follow_up().is_some_and(|due| due <= due_before))
+                .cloned()
+                .collect())
+        }
+    }
+
+    fn service(repo: &Arc<MemoryRepository>, tenant_id: Uuid, user_id: Uuid, permissions: &[&str]) -> DefaultCommunicationService {
+        let mut context = TenantContext::new(tenant_id, "tenant".to_string(), user_id);
+        context.permissions = permissions.iter().map(|p| p.to_string()).collect();
+        DefaultCommunicationService::new(repo.clone(), context)
+    }
+
+    fn call(subject: &str, follow_up_in_days: Option<i64>) -> CreateCommunicationRequest {
+        CreateCommunicationRequest {
+            communication_type: CommunicationType::Call,
+            direction: CommunicationDirection::Outbound,
+            subject: subject.to_string(),
+            body: Some("Discussed renewal".to_string()),
+            occurred_at: None,
+            follow_up_due: follow_up_in_days.map(|days| Utc::now() + Duration::days(days)),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_crud_is_scoped_to_author_and_tenant() {
+        let (tenant, other_tenant, customer) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let (alice, bob, manager) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let repo = Arc::new(MemoryRepository {
+            customers: vec![(tenant, customer), (other_tenant, customer)],
+            ..Default::default()
+        });
+        let rw = [PERMISSION_READ, PERMISSION_WRITE];
+
+        let alice_service = service(&repo, tenant, alice, &rw);
+        let bob_service = service(&repo, tenant, bob, &rw);
+
+        let entry = alice_service.log_communication(customer, call("Renewal call", None)).await.unwrap();
+        bob_service.log_communication(customer, call("Bob's visit", None)).await.unwrap();
+        repo.insert_communication(&CustomerCommunication::system_email(
+            tenant, customer, "buyer@example.com", "Invoice", None, None, Utc::now(),
+        )).await.unwrap();
+
+        // Own entries plus system-generated ones
+        let visible = alice_service.list_communications(customer, 50, 0).await.unwrap();
+        assert_eq!(visible.len(), 2);
+        assert!(visible.iter().all(|e| e.logged_by == Some(alice) || e.is_system_generated));
+
+        // Other users' entries look like they don't exist
+        assert!(matches!(
+            bob_service.get_communication(customer, entry.id).await,
+            Err(MasterDataError::CommunicationNotFound { .. })
+        ));
+        assert!(bob_service.delete_communication(customer, entry.id).await.is_err());
+
+        // Other tenants can't reach it, even with the same customer id
+        let foreign = service(&repo, other_tenant, alice, &[PERMISSION_MANAGE]);
+        assert!(foreign.get_communication(customer, entry.id).await.is_err());
+        assert!(foreign.list_communications(customer, 50, 0).await.unwrap().is_empty());
+
+        // Read-only users can't log
+        let reader = service(&repo, tenant, alice, &[PERMISSION_READ]);
+        assert!(matches!(
+            reader.log_communication(customer, call("Nope", None)).await,
+            Err(MasterDataError::PermissionDenied { .. })
+        ));
+
+        // Unknown customer
+        assert!(matches!(
+            alice_service.log_communication(Uuid::new_v4(), call("Nope", None)).await,
+            Err(MasterDataError::CustomerNotFound { .. })
+        ));
+
+        let updated = alice_service
+            .update_communication(customer, entry.id, UpdateCommunicationRequest {
+                subject: Some("Renewal call (signed)".to_string()),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(updated.subject, "Renewal call (signed)");
+        assert_eq!(alice_service.get_communication(customer, entry.id).await.unwrap().subject, "Renewal call (signed)");
+
+        // Managers see everything
+        let manager_service = service(&repo, tenant, manager, &[PERMISSION_MANAGE]);
+        assert_eq!(manager_service.list_communications(customer, 50, 0).await.unwrap().len(), 3);
+
+        alice_service.delete_communication(customer, entry.id).await.unwrap();
+        assert!(alice_service.get_communication(customer, entry.id).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_system_generated_entries_are_protected() {
+        let (tenant, customer, sender) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let repo = Arc::new(MemoryRepository { customers: vec![(tenant, customer)], ..Default::default() });
+        let system = CustomerCommunication::system_email(
+            tenant, customer, "buyer@example.com", "Quote Q-1001", Some("Please find attached"), Some(sender), Utc::now(),
+        );
+        repo.insert_communication(&system).await.unwrap();
+
+        let sender_service = service(&repo, tenant, sender, &[PERMISSION_READ, PERMISSION_WRITE]);
+        let listed = sender_service.list_communications(customer, 10, 0).await.unwrap();
+        assert_eq!(listed, vec![system.clone()]);
+
+        // The sender may schedule a follow-up but not rewrite or delete the record
+        let follow_up = Utc::now() + Duration::days(3);
+        let updated = sender_service
+            .update_communication(customer, system.id, UpdateCommunicationRequest {
+                follow_up_due: Some(follow_up),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(updated.open_follow_up(), Some(follow_up));
+        assert!(sender_service
+            .update_communication(customer, system.id, UpdateCommunicationRequest {
+                body: Some("edited".to_string()),
+                ..Default::default()
+            })
+            .await
+            .is_err());
+        assert!(matches!(
+            sender_service.delete_communication(customer, system.id).await,
+            Err(MasterDataError::PermissionDenied { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_my_follow_ups_span_customers() {
+        let tenant = Uuid::new_v4();
+        let (first, second) = (Uuid::new_v4(), Uuid::new_v4());
+        let (user, colleague) = (Uuid::new_v4(), Uuid::new_v4());
+        let repo = Arc::new(MemoryRepository {
+            customers: vec![(tenant, first), (tenant, second)],
+            ..Default::default()
+        });
+        let rw = [PERMISSION_READ, PERMISSION_WRITE];
+        let mine = service(&repo, tenant, user, &rw);
+
+        mine.log_communication(first, call("Overdue", Some(-2))).await.unwrap();
+        mine.log_communication(second, call("This week", Some(3))).await.unwrap();
+        mine.log_communication(second, call("Next quarter", Some(90))).await.unwrap();
+        service(&repo, tenant, colleague, &rw)
+            .log_communication(first, call("Colleague's", Some(-1)))
+            .await
+            .unwrap();
+
+        let due = mine.my_follow_ups(7).await.unwrap();
+        assert_eq!(due.overdue.len(), 1);
+        assert_eq!(due.overdue[0].customer_id, first);
+        assert_eq!(due.upcoming.len(), 1);
+        assert_eq!(due.upcoming[0].customer_id, second);
+    }
+}
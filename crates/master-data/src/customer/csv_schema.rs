@@ -0,0 +1,1150 @@
+//! # Customer CSV Schema
+//!
+//! The one flat CSV layout customers are exported in and imported from, so a
+//! tenant can export, edit the file in a spreadsheet and import it again.
+//! Export and import both go through the column tables below; a column is
+//! written and read by the same entry and cannot drift.
+//!
+//! One row per customer, in this column order:
+//!
+//! | Column | Content |
+//! |---|---|
+//! | `customer_number` | Generated on import when empty |
+//! | `legal_name` | Required |
+//! | `trade_names` | Separated by `\|`; a `\|` or `\` inside a name is escaped with `\` |
+//! | `customer_type` | Required, e.g. `B2b`, `Individual` |
+//! | `industry_classification`, `business_size`, `lifecycle_stage`, `status`, `credit_status`, `acquisition_channel` | Optional tokens |
+//! | `consolidation_group` | Optional text |
+//! | `currency_code`, `credit_limit`, `tax_exempt` | A credit limit or tax exemption needs the currency |
+//! | `address_<n>_type`, `_street_line_1`, `_street_line_2`, `_city`, `_state_province`, `_postal_code`, `_country_code`, `_is_primary` | Addresses 1 to [`CustomerCsvSchema::max_addresses`] |
+//! | `contact_<n>_type`, `_first_name`, `_last_name`, `_title`, `_department`, `_email`, `_phone`, `_mobile`, `_is_primary` | Contacts 1 to [`CustomerCsvSchema::max_contacts`] |
+//!
+//! Enumerations are written with the tokens the customer JSON API accepts
+//! (`NewCustomer`, `OnHold`, ...), booleans as `true`/`false`; an empty cell
+//! is an absent value. A group whose cells are all empty is no address or
+//! contact.
+//!
+//! Left out on purpose: tax numbers and bank accounts, which are encrypted
+//! at rest (see [`crate::customer::field_encryption`]) and must not leave in
+//! plain text, and references to other records by id (parent customer,
+//! corporate group, sales representative), which do not carry over to
+//! another tenant.
+//!
+//! Values starting with `=`, `+`, `-` or `@` are written with a leading `'`
+//! so spreadsheets do not run them as formulas (see
+//! [`erp_core::export_header::csv_field`]); the importer removes it again.
+//! Lines starting with `#` are the export header and are skipped.
+
+use rust_decimal::Decimal;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::customer::model::*;
+use crate::error::{MasterDataError, Result};
+use crate::types::*;
+use erp_core::export_header::{csv_field, ExportHeader};
+
+/// Address and contact groups when none is asked for
+pub const DEFAULT_MAX_GROUPS: usize = 3;
+/// Most address or contact groups a file may have
+pub const MAX_GROUPS: usize = 10;
+/// Most customers in one export
+pub const EXPORT_MAX_ROWS: usize = 10_000;
+/// Separator of the names in `trade_names`
+pub const TRADE_NAME_SEPARATOR: char = '|';
+
+/// Characters a spreadsheet starts a formula with
+const FORMULA_START: [char; 4] = ['=', '+', '-', '@'];
+
+type CellResult = std::result::Result<(), String>;
+
+/// A customer column, read from and written to a create request
+struct Column {
+    name: &'static str,
+    required: bool,
+    get: fn(&CreateCustomerRequest) -> String,
+    set: fn(&mut CreateCustomerRequest, &str) -> CellResult,
+}
+
+/// A column of a numbered address or contact group
+struct GroupColumn<T> {
+    suffix: &'static str,
+    /// Required once any cell of the group is filled
+    required: bool,
+    get: fn(&T) -> String,
+    set: fn(&mut T, &str) -> CellResult,
+}
+
+const CUSTOMER_COLUMNS: &[Column] = &[
+    Column {
+        name: "customer_number",
+        required: false,
+        get: |r| r.customer_number.clone().unwrap_or_default(),
+        set: |r, v| {
+            r.customer_number = optional_text(v);
+            Ok(())
+        },
+    },
+    Column {
+        name: "legal_name",
+        required: true,
+        get: |r| r.legal_name.clone(),
+        set: |r, v| {
+            r.legal_name = v.to_string();
+            Ok(())
+        },
+    },
+    Column {
+        name: "trade_names",
+        required: false,
+        get: |r| r.trade_names.as_deref().map(join_trade_names).unwrap_or_default(),
+        set: |r, v| {
+            r.trade_names = if v.is_empty() { None } else { Some(split_trade_names(v)) };
+            Ok(())
+        },
+    },
+    Column {
+        name: "customer_type",
+        required: true,
+        get: |r| token(&r.customer_type),
+        set: |r, v| {
+            r.customer_type = parse_token(v)?;
+            Ok(())
+        },
+    },
+    Column {
+        name: "industry_classification",
+        required: false,
+        get: |r| optional_token(&r.industry_classification),
+        set: |r, v| {
+            r.industry_classification = parse_optional_token(v)?;
+            Ok(())
+        },
+    },
+    Column {
+        name: "business_size",
+        required: false,
+        get: |r| optional_token(&r.business_size),
+        set: |r, v| {
+            r.business_size = parse_optional_token(v)?;
+            Ok(())
+        },
+    },
+    Column {
+        name: "lifecycle_stage",
+        required: false,
+        get: |r| optional_token(&r.lifecycle_stage),
+        set: |r, v| {
+            r.lifecycle_stage = parse_optional_token(v)?;
+            Ok(())
+        },
+    },
+    Column {
+        name: "status",
+        required: false,
+        get: |r| optional_token(&r.status),
+        set: |r, v| {
+            r.status = parse_optional_token(v)?;
+            Ok(())
+        },
+    },
+    Column {
+        name: "credit_status",
+        required: false,
+        get: |r| optional_token(&r.credit_status),
+        set: |r, v| {
+            r.credit_status = parse_optional_token(v)?;
+            Ok(())
+        },
+    },
+    Column {
+        name: "acquisition_channel",
+        required: false,
+        get: |r| optional_token(&r.acquisition_channel),
+        set: |r, v| {
+            r.acquisition_channel = parse_optional_token(v)?;
+            Ok(())
+        },
+    },
+    Column {
+        name: "consolidation_group",
+        required: false,
+        get: |r| r.consolidation_group.clone().unwrap_or_default(),
+        set: |r, v| {
+            r.consolidation_group = optional_text(v);
+            Ok(())
+        },
+    },
+    Column {
+        name: "currency_code",
+        required: false,
+        get: |r| r.financial_info.as_ref().map(|f| f.currency_code.clone()).unwrap_or_default(),
+        set: |r, v| {
+            if !v.is_empty() {
+                financial_info(r).currency_code = v.to_string();
+            }
+            Ok(())
+        },
+    },
+    Column {
+        name: "credit_limit",
+        required: false,
+        get: |r| {
+            r.financial_info.as_ref().and_then(|f| f.credit_limit).map(|limit| limit.to_string()).unwrap_or_default()
+        },
+        set: |r, v| {
+            if !v.is_empty() {
+                let limit = v.parse::<Decimal>().map_err(|_| format!("'{}' is not a decimal number", v))?;
+                financial_info(r).credit_limit = Some(limit);
+            }
+            Ok(())
+        },
+    },
+    Column {
+        name: "tax_exempt",
+        required: false,
+        get: |r| optional_bool(r.financial_info.as_ref().and_then(|f| f.tax_exempt)),
+        set: |r, v| {
+            if let Some(exempt) = parse_optional_bool(v)? {
+                financial_info(r).tax_exempt = Some(exempt);
+            }
+            Ok(())
+        },
+    },
+];
+
+const ADDRESS_COLUMNS: &[GroupColumn<CreateAddressRequest>] = &[
+    GroupColumn {
+        suffix: "type",
+        required: true,
+        get: |a| token(&a.address_type),
+        set: |a, v| {
+            a.address_type = parse_token(v)?;
+            Ok(())
+        },
+    },
+    GroupColumn {
+        suffix: "street_line_1",
+        required: true,
+        get: |a| a.street_line_1.clone(),
+        set: |a, v| {
+            a.street_line_1 = v.to_string();
+            Ok(())
+        },
+    },
+    GroupColumn {
+        suffix: "street_line_2",
+        required: false,
+        get: |a| a.street_line_2.clone().unwrap_or_default(),
+        set: |a, v| {
+            a.street_line_2 = optional_text(v);
+            Ok(())
+        },
+    },
+    GroupColumn {
+        suffix: "city",
+        required: true,
+        get: |a| a.city.clone(),
+        set: |a, v| {
+            a.city = v.to_string();
+            Ok(())
+        },
+    },
+    GroupColumn {
+        suffix: "state_province",
+        required: false,
+        get: |a| a.state_province.clone().unwrap_or_default(),
+        set: |a, v| {
+            a.state_province = optional_text(v);
+            Ok(())
+        },
+    },
+    GroupColumn {
+        suffix: "postal_code",
+        required: true,
+        get: |a| a.postal_code.clone(),
+        set: |a, v| {
+            a.postal_code = v.to_string();
+            Ok(())
+        },
+    },
+    GroupColumn {
+        suffix: "country_code",
+        required: true,
+        get: |a| a.country_code.clone(),
+        set: |a, v| {
+            a.country_code = v.to_string();
+            Ok(())
+        },
+    },
+    GroupColumn {
+        suffix: "is_primary",
+        required: false,
+        get: |a| optional_bool(a.is_primary),
+        set: |a, v| {
+            a.is_primary = parse_optional_bool(v)?;
+            Ok(())
+        },
+    },
+];
+
+const CONTACT_COLUMNS: &[GroupColumn<CreateContactRequest>] = &[
+    GroupColumn {
+        suffix: "type",
+        required: true,
+        get: |c| token(&c.contact_type),
+        set: |c, v| {
+            c.contact_type = parse_token(v)?;
+            Ok(())
+        },
+    },
+    GroupColumn {
+        suffix: "first_name",
+        required: true,
+        get: |c| c.first_name.clone(),
+        set: |c, v| {
+            c.first_name = v.to_string();
+            Ok(())
+        },
+    },
+    GroupColumn {
+        suffix: "last_name",
+        required: true,
+        get: |c| c.last_name.clone(),
+        set: |c, v| {
+            c.last_name = v.to_string();
+            Ok(())
+        },
+    },
+    GroupColumn {
+        suffix: "title",
+        required: false,
+        get: |c| c.title.clone().unwrap_or_default(),
+        set: |c, v| {
+            c.title = optional_text(v);
+            Ok(())
+        },
+    },
+    GroupColumn {
+        suffix: "department",
+        required: false,
+        get: |c| c.department.clone().unwrap_or_default(),
+        set: |c, v| {
+            c.department = optional_text(v);
+            Ok(())
+        },
+    },
+    GroupColumn {
+        suffix: "email",
+        required: false,
+        get: |c| c.email.clone().unwrap_or_default(),
+        set: |c, v| {
+            c.email = optional_text(v);
+            Ok(())
+        },
+    },
+    GroupColumn {
+        suffix: "phone",
+        required: false,
+        get: |c| c.phone.clone().unwrap_or_default(),
+        set: |c, v| {
+            c.phone = optional_text(v);
+            Ok(())
+        },
+    },
+    GroupColumn {
+        suffix: "mobile",
+        required: false,
+        get: |c| c.mobile.clone().unwrap_or_default(),
+        set: |c, v| {
+            c.mobile = optional_text(v);
+            Ok(())
+        },
+    },
+    GroupColumn {
+        suffix: "is_primary",
+        required: false,
+        get: |c| optional_bool(c.is_primary),
+        set: |c, v| {
+            c.is_primary = parse_optional_bool(v)?;
+            Ok(())
+        },
+    },
+];
+
+fn optional_text(value: &str) -> Option<String> {
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+/// The JSON token of an enumeration value
+fn token<T: Serialize>(value: &T) -> String {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::String(token)) => token,
+        _ => String::new(),
+    }
+}
+
+fn optional_token<T: Serialize>(value: &Option<T>) -> String {
+    value.as_ref().map(token).unwrap_or_default()
+}
+
+fn parse_token<T: DeserializeOwned>(value: &str) -> std::result::Result<T, String> {
+    if value.is_empty() {
+        return Err("A value is required".to_string());
+    }
+    serde_json::from_value(serde_json::Value::String(value.to_string()))
+        .map_err(|_| format!("'{}' is not one of the accepted values", value))
+}
+
+fn parse_optional_token<T: DeserializeOwned>(value: &str) -> std::result::Result<Option<T>, String> {
+    if value.is_empty() {
+        return Ok(None);
+    }
+    parse_token(value).map(Some)
+}
+
+fn optional_bool(value: Option<bool>) -> String {
+    value.map(|b| b.to_string()).unwrap_or_default()
+}
+
+fn parse_optional_bool(value: &str) -> std::result::Result<Option<bool>, String> {
+    match value {
+        "" => Ok(None),
+        "true" => Ok(Some(true)),
+        "false" => Ok(Some(false)),
+        _ => Err(format!("'{}' is neither true nor false", value)),
+    }
+}
+
+fn financial_info(request: &mut CreateCustomerRequest) -> &mut CreateFinancialInfoRequest {
+    request.financial_info.get_or_insert_with(|| CreateFinancialInfoRequest {
+        currency_code: String::new(),
+        credit_limit: None,
+        payment_terms: None,
+        tax_exempt: None,
+        bank_accounts: None,
+    })
+}
+
+fn join_trade_names(names: &[String]) -> String {
+    names
+        .iter()
+        .map(|name| name.replace('\\', "\\\\").replace(TRADE_NAME_SEPARATOR, "\\|"))
+        .collect::<Vec<_>>()
+        .join("|")
+}
+
+fn split_trade_names(value: &str) -> Vec<String> {
+    let mut names = vec![String::new()];
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => names.last_mut().unwrap().extend(chars.next()),
+            TRADE_NAME_SEPARATOR => names.push(String::new()),
+            c => names.last_mut().unwrap().push(c),
+        }
+    }
+    names
+}
+
+/// A cell as written: formula guard, then CSV quoting; a leading `#` is
+/// quoted so the row is not taken for a header line
+fn encode_cell(value: &str) -> String {
+    // A value that already starts with the guard gets one more, so the importer's removal restores it
+    let guarded = if value.starts_with('\'') && value.trim_start_matches('\'').starts_with(FORMULA_START) {
+        format!("'{}", value)
+    } else {
+        value.to_string()
+    };
+    let field = csv_field(&guarded);
+    if field.starts_with('#') {
+        format!("\"{}\"", field)
+    } else {
+        field
+    }
+}
+
+/// A cell as read, without the formula guard of [`encode_cell`]
+fn decode_cell(value: String) -> String {
+    if value.starts_with('\'') && value.trim_start_matches('\'').starts_with(FORMULA_START) {
+        value[1..].to_string()
+    } else {
+        value
+    }
+}
+
+/// A problem with the file or one of its rows
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CsvRowError {
+    /// Line the row starts on, counting from 1
+    pub line: usize,
+    pub column: Option<String>,
+    pub message: String,
+}
+
+impl CsvRowError {
+    fn new(line: usize, column: Option<&str>, message: impl Into<String>) -> Self {
+        Self { line, column: column.map(str::to_string), message: message.into() }
+    }
+}
+
+/// A row that reads as a customer
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportedCustomer {
+    pub line: usize,
+    pub request: CreateCustomerRequest,
+}
+
+/// The customers of a file, and what kept other rows from being read
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CustomerCsvImport {
+    pub customers: Vec<ImportedCustomer>,
+    pub errors: Vec<CsvRowError>,
+}
+
+impl CustomerCsvImport {
+    pub fn is_clean(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    fn failed(error: CsvRowError) -> Self {
+        Self { customers: Vec::new(), errors: vec![error] }
+    }
+}
+
+/// Records of a CSV text with the line each starts on; blank lines and
+/// lines starting with `#` outside quotes are skipped
+fn records(text: &str) -> std::result::Result<Vec<(usize, Vec<String>)>, CsvRowError> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let mut records = Vec::new();
+    let mut chars = text.chars().peekable();
+    let mut line = 1;
+
+    while chars.peek().is_some() {
+        let start = line;
+        if chars.peek() == Some(&'#') {
+            for c in chars.by_ref() {
+                if c == '\n' {
+                    break;
+                }
+            }
+            line += 1;
+            continue;
+        }
+
+        let mut fields = Vec::new();
+        let mut field = String::new();
+        let mut quoted = false;
+        loop {
+            match chars.next() {
+                None => {
+                    if quoted {
+                        return Err(CsvRowError::new(start, None, "A quoted value is not closed"));
+                    }
+                    fields.push(std::mem::take(&mut field));
+                    break;
+                }
+                Some('"') if quoted => {
+                    if chars.peek() == Some(&'"') {
+                        chars.next();
+                        field.push('"');
+                    } else {
+                        quoted = false;
+                    }
+                }
+                Some('"') if field.is_empty() => quoted = true,
+                Some(c) if quoted => {
+                    if c == '\n' {
+                        line += 1;
+                    }
+                    field.push(c);
+                }
+                Some(',') => fields.push(std::mem::take(&mut field)),
+                Some('\r') if chars.peek() == Some(&'\n') => {}
+                Some('\n') => {
+                    line += 1;
+                    fields.push(std::mem::take(&mut field));
+                    break;
+                }
+                Some(c) => field.push(c),
+            }
+        }
+
+        let blank = fields.len() == 1 && fields[0].is_empty();
+        if !blank {
+            records.push((start, fields.into_iter().map(decode_cell).collect()));
+        }
+    }
+    Ok(records)
+}
+
+/// The canonical customer CSV layout with its number of address and contact groups
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CustomerCsvSchema {
+    pub max_addresses: usize,
+    pub max_contacts: usize,
+}
+
+impl Default for CustomerCsvSchema {
+    fn default() -> Self {
+        Self { max_addresses: DEFAULT_MAX_GROUPS, max_contacts: DEFAULT_MAX_GROUPS }
+    }
+}
+
+impl CustomerCsvSchema {
+    pub fn new(max_addresses: usize, max_contacts: usize) -> Result<Self> {
+        for (field, groups) in [("max_addresses", max_addresses), ("max_contacts", max_contacts)] {
+            if groups > MAX_GROUPS {
+                return Err(MasterDataError::ValidationError {
+                    field: field.to_string(),
+                    message: format!("At most {} groups are supported", MAX_GROUPS),
+                });
+            }
+        }
+        Ok(Self { max_addresses, max_contacts })
+    }
+
+    /// The widest layout, accepting any file within [`MAX_GROUPS`]
+    pub fn widest() -> Self {
+        Self { max_addresses: MAX_GROUPS, max_contacts: MAX_GROUPS }
+    }
+
+    /// The default layout, widened as far as the customers need
+    pub fn fitting(customers: &[Customer]) -> Self {
+        let addresses = customers.iter().map(|c| c.addresses.len()).max().unwrap_or(0);
+        let contacts = customers.iter().map(|c| c.contacts.len()).max().unwrap_or(0);
+        Self {
+            max_addresses: addresses.clamp(DEFAULT_MAX_GROUPS, MAX_GROUPS),
+            max_contacts: contacts.clamp(DEFAULT_MAX_GROUPS, MAX_GROUPS),
+        }
+    }
+
+    /// Column names in file order
+    pub fn columns(&self) -> Vec<String> {
+        let mut columns: Vec<String> = CUSTOMER_COLUMNS.iter().map(|c| c.name.to_string()).collect();
+        for n in 1..=self.max_addresses {
+            columns.extend(ADDRESS_COLUMNS.iter().map(|c| format!("address_{}_{}", n, c.suffix)));
+        }
+        for n in 1..=self.max_contacts {
+            columns.extend(CONTACT_COLUMNS.iter().map(|c| format!("contact_{}_{}", n, c.suffix)));
+        }
+        columns
+    }
+
+    /// Cells of a customer in column order, before quoting; `None` when it
+    /// has more addresses or contacts than the layout has groups
+    pub fn row(&self, request: &CreateCustomerRequest) -> Option<Vec<String>> {
+        let addresses = request.addresses.as_deref().unwrap_or_default();
+        let contacts = request.contacts.as_deref().unwrap_or_default();
+        if addresses.len() > self.max_addresses || contacts.len() > self.max_contacts {
+            return None;
+        }
+
+        let mut cells: Vec<String> = CUSTOMER_COLUMNS.iter().map(|c| (c.get)(request)).collect();
+        for n in 0..self.max_addresses {
+            match addresses.get(n) {
+                Some(address) => cells.extend(ADDRESS_COLUMNS.iter().map(|c| (c.get)(address))),
+                None => cells.extend(ADDRESS_COLUMNS.iter().map(|_| String::new())),
+            }
+        }
+        for n in 0..self.max_contacts {
+            match contacts.get(n) {
+                Some(contact) => cells.extend(CONTACT_COLUMNS.iter().map(|c| (c.get)(contact))),
+                None => cells.extend(CONTACT_COLUMNS.iter().map(|_| String::new())),
+            }
+        }
+        Some(cells)
+    }
+
+    /// The export: header rows, the column row, then one row per customer
+    pub fn export(&self, header: &ExportHeader, customers: &[Customer]) -> Result<String> {
+        let mut csv = header.csv_rows();
+        csv.push_str(&self.columns().join(","));
+        csv.push('\n');
+        for customer in customers {
+            let cells = self.row(&request_from_customer(customer)).ok_or_else(|| MasterDataError::ValidationError {
+                field: "max_addresses".to_string(),
+                message: format!(
+                    "Customer {} has {} addresses and {} contacts, more than the {} and {} column groups of the export",
+                    customer.customer_number,
+                    customer.addresses.len(),
+                    customer.contacts.len(),
+                    self.max_addresses,
+                    self.max_contacts
+                ),
+            })?;
+            csv.push_str(&cells.iter().map(|cell| encode_cell(cell)).collect::<Vec<_>>().join(","));
+            csv.push('\n');
+        }
+        Ok(csv)
+    }
+
+    /// Read every row of a file; a row with any error is left out and reported
+    pub fn import(&self, text: &str) -> CustomerCsvImport {
+        self.read(text, false)
+    }
+
+    /// Read the file as [`Self::import`] does and also report every cell that
+    /// would not be exported again exactly as it is, for example an address
+    /// group after an empty one
+    pub fn validate_only(&self, text: &str) -> CustomerCsvImport {
+        self.read(text, true)
+    }
+
+    fn read(&self, text: &str, check_lossless: bool) -> CustomerCsvImport {
+        let records = match records(text) {
+            Ok(records) => records,
+            Err(e) => return CustomerCsvImport::failed(e),
+        };
+        let mut records = records.into_iter();
+        let Some((header_line, header)) = records.next() else {
+            return CustomerCsvImport::failed(CsvRowError::new(1, None, "The file has no column row"));
+        };
+
+        let known = self.columns();
+        let mut positions = std::collections::HashMap::new();
+        for (position, name) in header.iter().enumerate() {
+            if !known.contains(name) {
+                let message = if name.starts_with("address_") || name.starts_with("contact_") {
+                    format!(
+                        "Unknown column; at most {} addresses and {} contacts are accepted",
+                        self.max_addresses, self.max_contacts
+                    )
+                } else {
+                    "Unknown column".to_string()
+                };
+                return CustomerCsvImport::failed(CsvRowError::new(header_line, Some(name), message));
+            }
+            if positions.insert(name.as_str(), position).is_some() {
+                return CustomerCsvImport::failed(CsvRowError::new(header_line, Some(name), "Duplicate column"));
+            }
+        }
+        if let Some(missing) = CUSTOMER_COLUMNS.iter().find(|c| c.required && !positions.contains_key(c.name)) {
+            return CustomerCsvImport::failed(CsvRowError::new(header_line, Some(missing.name), "Required column is missing"));
+        }
+
+        let mut import = CustomerCsvImport::default();
+        for (line, cells) in records {
+            if cells.len() > header.len() {
+                import.errors.push(CsvRowError::new(
+                    line,
+                    None,
+                    format!("The row has {} values but there are {} columns", cells.len(), header.len()),
+                ));
+                continue;
+            }
+            let cell = |name: &str| positions.get(name).and_then(|&p| cells.get(p)).map(String::as_str).unwrap_or("");
+
+            let errors_before = import.errors.len();
+            let request = self.request_from_cells(line, &cell, &mut import.errors);
+            if import.errors.len() > errors_before {
+                continue;
+            }
+
+            if check_lossless {
+                let columns = self.columns();
+                let exported = self.row(&request).unwrap_or_default();
+                for (name, value) in columns.iter().zip(exported) {
+                    if cell(name) != value {
+                        import.errors.push(CsvRowError::new(
+                            line,
+                            Some(name),
+                            format!("'{}' would be exported again as '{}'", cell(name), value),
+                        ));
+                    }
+                }
+                if import.errors.len() > errors_before {
+                    continue;
+                }
+            }
+            import.customers.push(ImportedCustomer { line, request });
+        }
+        import
+    }
+
+    fn request_from_cells<'a>(
+        &self,
+        line: usize,
+        cell: &dyn Fn(&str) -> &'a str,
+        errors: &mut Vec<CsvRowError>,
+    ) -> CreateCustomerRequest {
+        let mut request = blank_request();
+        for column in CUSTOMER_COLUMNS {
+            let value = cell(column.name);
+            if column.required && value.is_empty() {
+                errors.push(CsvRowError::new(line, Some(column.name), "A value is required"));
+            } else if let Err(message) = (column.set)(&mut request, value) {
+                errors.push(CsvRowError::new(line, Some(column.name), message));
+            }
+        }
+        if request.financial_info.as_ref().is_some_and(|f| f.currency_code.is_empty()) {
+            errors.push(CsvRowError::new(
+                line,
+                Some("currency_code"),
+                "A credit limit or tax exemption needs the currency",
+            ));
+        }
+
+        let addresses = read_groups(line, "address", self.max_addresses, ADDRESS_COLUMNS, blank_address, cell, errors);
+        let contacts = read_groups(line, "contact", self.max_contacts, CONTACT_COLUMNS, blank_contact, cell, errors);
+        request.addresses = (!addresses.is_empty()).then_some(addresses);
+        request.contacts = (!contacts.is_empty()).then_some(contacts);
+
+        if let Err(violations) = request.validate() {
+            errors.push(CsvRowError::new(line, None, violations.to_string()));
+        }
+        request
+    }
+}
+
+/// The filled groups of a row in order
+fn read_groups<'a, T>(
+    line: usize,
+    prefix: &str,
+    groups: usize,
+    columns: &[GroupColumn<T>],
+    blank: fn() -> T,
+    cell: &dyn Fn(&str) -> &'a str,
+    errors: &mut Vec<CsvRowError>,
+) -> Vec<T> {
+    let mut items = Vec::new();
+    for n in 1..=groups {
+        let names: Vec<String> = columns.iter().map(|c| format!("{}_{}_{}", prefix, n, c.suffix)).collect();
+        if names.iter().all(|name| cell(name).is_empty()) {
+            continue;
+        }
+        let mut item = blank();
+        for (column, name) in columns.iter().zip(&names) {
+            let value = cell(name);
+            if column.required && value.is_empty() {
+                errors.push(CsvRowError::new(line, Some(name), "A value is required when the group is filled"));
+            } else if let Err(message) = (column.set)(&mut item, value) {
+                errors.push(CsvRowError::new(line, Some(name), message));
+            }
+        }
+        items.push(item);
+    }
+    items
+}
+
+/// Starting point of a row; every required column overwrites its placeholder
+fn blank_request() -> CreateCustomerRequest {
+    CreateCustomerRequest {
+        customer_number: None,
+        legal_name: String::new(),
+        trade_names: None,
+        customer_type: CustomerType::Business,
+        industry_classification: None,
+        business_size: None,
+        parent_customer_id: None,
+        corporate_group_id: None,
+        customer_hierarchy_level: None,
+        consolidation_group: None,
+        lifecycle_stage: None,
+        status: None,
+        credit_status: None,
+        addresses: None,
+        contacts: None,
+        tax_jurisdictions: None,
+        tax_numbers: None,
+        financial_info: None,
+        sales_representative_id: None,
+        account_manager_id: None,
+        acquisition_channel: None,
+        external_ids: None,
+        sync_info: None,
+    }
+}
+
+fn blank_address() -> CreateAddressRequest {
+    CreateAddressRequest {
+        address_type: AddressType::Other,
+        street_line_1: String::new(),
+        street_line_2: None,
+        city: String::new(),
+        state_province: None,
+        postal_code: String::new(),
+        country_code: String::new(),
+        coordinates: None,
+        is_primary: None,
+    }
+}
+
+fn blank_contact() -> CreateContactRequest {
+    CreateContactRequest {
+        contact_type: ContactType::Other,
+        first_name: String::new(),
+        last_name: String::new(),
+        title: None,
+        department: None,
+        email: None,
+        phone: None,
+        mobile: None,
+        preferred_language: None,
+        communication_preferences: None,
+        is_primary: None,
+    }
+}
+
+/// The create request that makes a customer again, limited to what the CSV layout holds
+pub fn request_from_customer(customer: &Customer) -> CreateCustomerRequest {
+    let financial = &customer.financial_info;
+    CreateCustomerRequest {
+        customer_number: Some(customer.customer_number.clone()),
+        legal_name: customer.legal_name.clone(),
+        trade_names: (!customer.trade_names.is_empty()).then(|| customer.trade_names.clone()),
+        customer_type: customer.customer_type.clone(),
+        industry_classification: Some(customer.industry_classification.clone()),
+        business_size: Some(customer.business_size.clone()),
+        consolidation_group: customer.consolidation_group.clone(),
+        lifecycle_stage: Some(customer.lifecycle_stage.clone()),
+        status: Some(customer.status.clone()),
+        credit_status: Some(customer.credit_status.clone()),
+        addresses: (!customer.addresses.is_empty()).then(|| {
+            customer
+                .addresses
+                .iter()
+                .map(|a| CreateAddressRequest {
+                    address_type: a.address_type,
+                    street_line_1: a.street_line_1.clone(),
+                    street_line_2: a.street_line_2.clone(),
+                    city: a.city.clone(),
+                    state_province: a.state_province.clone(),
+                    postal_code: a.postal_code.clone(),
+                    country_code: a.country_code.clone(),
+                    coordinates: None,
+                    is_primary: Some(a.is_primary),
+                })
+                .collect()
+        }),
+        contacts: (!customer.contacts.is_empty()).then(|| {
+            customer
+                .contacts
+                .iter()
+                .map(|c| CreateContactRequest {
+                    contact_type: c.contact_type,
+                    first_name: c.first_name.clone(),
+                    last_name: c.last_name.clone(),
+                    title: c.title.clone(),
+                    department: c.department.clone(),
+                    email: c.email.clone(),
+                    phone: c.phone.clone(),
+                    mobile: c.mobile.clone(),
+                    preferred_language: None,
+                    communication_preferences: None,
+                    is_primary: Some(c.is_primary),
+                })
+                .collect()
+        }),
+        financial_info: Some(CreateFinancialInfoRequest {
+            currency_code: financial.currency_code.clone(),
+            credit_limit: financial.credit_limit,
+            payment_terms: None,
+            tax_exempt: Some(financial.tax_exempt),
+            bank_accounts: None,
+        }),
+        acquisition_channel: customer.acquisition_channel.clone(),
+        ..blank_request()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn address(customer: &Customer, address_type: AddressType, street: &str, street_2: Option<&str>, primary: bool) -> Address {
+        Address {
+            id: Uuid::new_v4(),
+            entity_type: "customer".to_string(),
+            entity_id: customer.id,
+            address_type,
+            street_line_1: street.to_string(),
+            street_line_2: street_2.map(str::to_string),
+            city: "Berlin".to_string(),
+            state_province: None,
+            postal_code: "10115".to_string(),
+            country_code: "DE".to_string(),
+            coordinates: None,
+            is_primary: primary,
+            is_active: true,
+            audit: customer.audit.clone(),
+        }
+    }
+
+    fn contact(customer: &Customer, first_name: &str, last_name: &str, title: Option<&str>) -> ContactInfo {
+        ContactInfo {
+            id: Uuid::new_v4(),
+            entity_type: "customer".to_string(),
+            entity_id: customer.id,
+            contact_type: ContactType::Billing,
+            first_name: first_name.to_string(),
+            last_name: last_name.to_string(),
+            title: title.map(str::to_string),
+            department: None,
+            email: Some(format!("{}@example.com", first_name.to_lowercase())),
+            phone: Some("+49 30 1234567".to_string()),
+            mobile: None,
+            fax: None,
+            preferred_language: None,
+            communication_preferences: None,
+            website: None,
+            social_media_accounts: None,
+            timezone: None,
+            notes: None,
+            tags: vec![],
+            is_primary: false,
+            is_active: true,
+            audit: customer.audit.clone(),
+        }
+    }
+
+    fn seeded_customers() -> Vec<Customer> {
+        let mut full = Customer {
+            customer_number: "CUST-0001".to_string(),
+            legal_name: "Müller, Schmidt & \"Partner\" GmbH".to_string(),
+            trade_names: vec!["M|S".to_string(), "Back\\slash".to_string(), "Plain".to_string()],
+            customer_type: CustomerType::B2b,
+            industry_classification: IndustryClassification::Manufacturing,
+            business_size: BusinessSize::Medium,
+            consolidation_group: Some("=SUM(A1)".to_string()),
+            lifecycle_stage: CustomerLifecycleStage::NewCustomer,
+            credit_status: CreditStatus::OnHold,
+            acquisition_channel: Some(AcquisitionChannel::TradeShow),
+            ..Default::default()
+        };
+        full.financial_info.currency_code = "EUR".to_string();
+        full.financial_info.credit_limit = Some("15000.50".parse().unwrap());
+        full.financial_info.tax_exempt = true;
+        full.addresses = vec![
+            address(&full, AddressType::Billing, "Hauptstraße 1", None, true),
+            address(&full, AddressType::Shipping, "Hof 2\nEingang B", Some("Tor \"West\""), false),
+        ];
+        full.contacts = vec![
+            contact(&full, "Anna", "O'Brien", Some("'=not a formula")),
+            contact(&full, "Ben", "Meier, jun.", None),
+        ];
+
+        let minimal = Customer {
+            customer_number: "#42".to_string(),
+            legal_name: "Solo".to_string(),
+            customer_type: CustomerType::Individual,
+            ..Default::default()
+        };
+
+        let mut many_addresses = Customer {
+            customer_number: "-7".to_string(),
+            legal_name: "@Home Ltd".to_string(),
+            ..Default::default()
+        };
+        many_addresses.addresses = (1..=DEFAULT_MAX_GROUPS)
+            .map(|n| address(&many_addresses, AddressType::Warehouse, &format!("Lager {}", n), None, n == 1))
+            .collect();
+
+        vec![full, minimal, many_addresses]
+    }
+
+    fn export(customers: &[Customer]) -> String {
+        let header = ExportHeader::new("Customers", None, "tester");
+        CustomerCsvSchema::fitting(customers).export(&header, customers).unwrap()
+    }
+
+    #[test]
+    fn test_round_trip_preserves_every_column() {
+        let customers = seeded_customers();
+        let csv = export(&customers);
+
+        let import = CustomerCsvSchema::default().validate_only(&csv);
+        assert!(import.is_clean(), "{:?}", import.errors);
+        assert_eq!(import.customers.len(), customers.len());
+
+        // What a fresh tenant is asked to create equals the exported customer field for field
+        for (original, imported) in customers.iter().zip(&import.customers) {
+            assert_eq!(
+                serde_json::to_value(request_from_customer(original)).unwrap(),
+                serde_json::to_value(&imported.request).unwrap()
+            );
+        }
+
+        let full = &import.customers[0].request;
+        assert_eq!(full.legal_name, "Müller, Schmidt & \"Partner\" GmbH");
+        assert_eq!(full.trade_names.as_deref().unwrap(), ["M|S", "Back\\slash", "Plain"]);
+        assert_eq!(full.consolidation_group.as_deref(), Some("=SUM(A1)"));
+        let addresses = full.addresses.as_ref().unwrap();
+        assert_eq!(addresses[1].street_line_1, "Hof 2\nEingang B");
+        assert_eq!(addresses[1].street_line_2.as_deref(), Some("Tor \"West\""));
+        assert_eq!(addresses[0].state_province, None);
+        assert_eq!(full.contacts.as_ref().unwrap()[0].title.as_deref(), Some("'=not a formula"));
+
+        let minimal = &import.customers[1].request;
+        assert_eq!(minimal.customer_number.as_deref(), Some("#42"));
+        assert!(minimal.trade_names.is_none() && minimal.addresses.is_none() && minimal.contacts.is_none());
+        assert!(minimal.acquisition_channel.is_none() && minimal.consolidation_group.is_none());
+        assert_eq!(minimal.financial_info.as_ref().unwrap().credit_limit, None);
+
+        assert_eq!(import.customers[2].request.customer_number.as_deref(), Some("-7"));
+        assert_eq!(import.customers[2].request.addresses.as_ref().unwrap().len(), DEFAULT_MAX_GROUPS);
+
+        // Formulas never reach a spreadsheet unguarded
+        assert!(csv.contains(",'=SUM(A1),"));
+        assert!(csv.contains("\n'-7,'@Home Ltd,"));
+    }
+
+    #[test]
+    fn test_validate_only_reports_cells_that_would_change() {
+        let schema = CustomerCsvSchema::new(2, 0).unwrap();
+        let columns = schema.columns();
+        let mut row = vec![String::new(); columns.len()];
+        let mut set = |name: &str, value: &str| {
+            row[columns.iter().position(|c| c == name).unwrap()] = value.to_string();
+        };
+        set("legal_name", "Gap GmbH");
+        set("customer_type", "B2b");
+        set("address_2_type", "Billing");
+        set("address_2_street_line_1", "Ring 5");
+        set("address_2_city", "Köln");
+        set("address_2_postal_code", "50667");
+        set("address_2_country_code", "DE");
+        let csv = format!("{}\n{}\n", columns.join(","), row.join(","));
+
+        // Importing keeps the address, validating points out it moves to the first group
+        assert!(schema.import(&csv).is_clean());
+        let check = schema.validate_only(&csv);
+        assert!(check.customers.is_empty());
+        assert!(check.errors.iter().any(|e| e.column.as_deref() == Some("address_1_city") && e.line == 2));
+        assert!(check.errors.iter().any(|e| e.column.as_deref() == Some("address_2_city")));
+    }
+
+    #[test]
+    fn test_import_reports_bad_cells_by_line_and_column() {
+        let csv = "legal_name,customer_type,tax_exempt,credit_limit\n\
+                   Good,B2c,,\n\
+                   Bad,b2c,TRUE,\n\
+                   \"Also bad\",B2c,,12.5\n";
+        let import = CustomerCsvSchema::default().import(csv);
+
+        assert_eq!(import.customers.len(), 1);
+        assert_eq!(import.customers[0].line, 2);
+        let errors: Vec<_> = import.errors.iter().map(|e| (e.line, e.column.as_deref().unwrap_or(""))).collect();
+        assert_eq!(errors, [(3, "customer_type"), (3, "tax_exempt"), (4, "currency_code")]);
+    }
+
+    #[test]
+    fn test_file_layout_must_match_the_schema() {
+        let schema = CustomerCsvSchema::default();
+
+        let too_many = schema.import("legal_name,customer_type,address_4_city\nA,B2b,Bonn\n");
+        assert_eq!(too_many.errors[0].column.as_deref(), Some("address_4_city"));
+        assert!(too_many.customers.is_empty());
+
+        let missing = schema.import("customer_number,customer_type\nC-1,B2b\n");
+        assert_eq!(missing.errors[0].column.as_deref(), Some("legal_name"));
+
+        let mut crowded = Customer { legal_name: "Crowded".to_string(), ..Default::default() };
+        crowded.addresses = (0..2).map(|_| address(&crowded, AddressType::Branch, "Weg 1", None, false)).collect();
+        let header = ExportHeader::new("Customers", None, "tester");
+        assert!(CustomerCsvSchema::new(1, 1).unwrap().export(&header, &[crowded]).is_err());
+        assert!(CustomerCsvSchema::new(MAX_GROUPS + 1, 1).is_err());
+    }
+}
@@ -0,0 +1,181 @@
+//! # Customer Search Cursors
+//!
+//! Customer searches can be paged with a cursor instead of a page number. The
+//! cursor names the last customer of a page by its sort key and id, and the
+//! next page is read with `WHERE (sort_column, id) > (key, id)` (`<` when
+//! sorting descending) rather than `OFFSET`, so late pages cost as much as the
+//! first and customers created in between do not shift the pages.
+//!
+//! Clients get the cursor as opaque URL-safe base64 and pass it back
+//! unchanged. It is only valid for the sort it was made for.
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::customer::model::{Customer, CustomerSearchCriteria, CustomerSortField, SortOrder};
+use crate::error::{MasterDataError, Result};
+
+/// Page size of cursor searches that ask for none
+pub const DEFAULT_CURSOR_PAGE_SIZE: u32 = 50;
+
+/// Column a customer search is ordered by, with the id as tiebreaker
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CustomerSortKey {
+    #[default]
+    LegalName,
+    CustomerNumber,
+    CreatedAt,
+    ModifiedAt,
+}
+
+impl CustomerSortKey {
+    /// Fields without a column of their own (analytics figures) sort by legal name
+    pub fn of(field: Option<&CustomerSortField>) -> Self {
+        match field {
+            Some(CustomerSortField::CustomerNumber) => Self::CustomerNumber,
+            Some(CustomerSortField::CreatedAt) => Self::CreatedAt,
+            Some(CustomerSortField::ModifiedAt) => Self::ModifiedAt,
+            _ => Self::LegalName,
+        }
+    }
+
+    pub fn column(self) -> &'static str {
+        match self {
+            Self::LegalName => "legal_name",
+            Self::CustomerNumber => "customer_number",
+            Self::CreatedAt => "created_at",
+            Self::ModifiedAt => "updated_at",
+        }
+    }
+
+    /// Type the key, kept as text in the cursor, is cast to before comparing
+    pub fn sql_type(self) -> &'static str {
+        match self {
+            Self::LegalName | Self::CustomerNumber => "text",
+            Self::CreatedAt | Self::ModifiedAt => "timestamptz",
+        }
+    }
+}
+
+/// Order of a customer search
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct CustomerOrder {
+    pub key: CustomerSortKey,
+    pub descending: bool,
+}
+
+impl CustomerOrder {
+    pub fn of(criteria: &CustomerSearchCriteria) -> Self {
+        Self {
+            key: CustomerSortKey::of(criteria.sort_by.as_ref()),
+            descending: matches!(criteria.sort_order, Some(SortOrder::Desc)),
+        }
+    }
+
+    pub fn direction(self) -> &'static str {
+        if self.descending { "DESC" } else { "ASC" }
+    }
+
+    /// Row comparison selecting the customers after a cursor
+    pub fn after(self) -> &'static str {
+        if self.descending { "<" } else { ">" }
+    }
+}
+
+/// Position after the last customer of a page
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CustomerCursor {
+    pub order: CustomerOrder,
+    /// Sort key of the last customer, as the database prints it
+    pub key: String,
+    pub id: Uuid,
+}
+
+impl CustomerCursor {
+    pub fn new(order: CustomerOrder, key: impl Into<String>, id: Uuid) -> Self {
+        Self { order, key: key.into(), id }
+    }
+
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).expect("cursor serializes"))
+    }
+
+    pub fn decode(cursor: &str) -> Result<Self> {
+        URL_SAFE_NO_PAD
+            .decode(cursor)
+            .ok()
+            .and_then(|json| serde_json::from_slice(&json).ok())
+            .ok_or_else(|| invalid("not a cursor of a customer search"))
+    }
+
+    /// The cursor of `criteria`, if any, checked against the order it asks for
+    pub fn of(criteria: &CustomerSearchCriteria, order: CustomerOrder) -> Result<Option<Self>> {
+        let Some(cursor) = criteria.cursor.as_deref() else {
+            return Ok(None);
+        };
+        let cursor = Self::decode(cursor)?;
+        if cursor.order != order {
+            return Err(invalid("made for a different sort order"));
+        }
+        Ok(Some(cursor))
+    }
+}
+
+/// One page of a customer search
+#[derive(Debug, Clone, Default)]
+pub struct CustomerPage {
+    pub customers: Vec<Customer>,
+    /// Set when more customers match
+    pub next_cursor: Option<String>,
+}
+
+fn invalid(message: &str) -> MasterDataError {
+    MasterDataError::ValidationError { field: "cursor".to_string(), message: message.to_string() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cursor_round_trips_and_is_bound_to_its_order() {
+        let order = CustomerOrder { key: CustomerSortKey::CreatedAt, descending: true };
+        let cursor = CustomerCursor::new(order, "2026-03-01 09:30:00.123456+00", Uuid::new_v4());
+        let encoded = cursor.encode();
+        assert!(encoded.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+        assert_eq!(CustomerCursor::decode(&encoded).unwrap(), cursor);
+
+        let criteria = CustomerSearchCriteria {
+            cursor: Some(encoded.clone()),
+            sort_by: Some(CustomerSortField::CreatedAt),
+            sort_order: Some(SortOrder::Desc),
+            ..Default::default()
+        };
+        assert_eq!(CustomerCursor::of(&criteria, CustomerOrder::of(&criteria)).unwrap(), Some(cursor));
+
+        let by_name = CustomerSearchCriteria { cursor: Some(encoded), ..Default::default() };
+        assert!(matches!(
+            CustomerCursor::of(&by_name, CustomerOrder::of(&by_name)),
+            Err(MasterDataError::ValidationError { field, .. }) if field == "cursor"
+        ));
+    }
+
+    #[test]
+    fn test_malformed_cursor_is_a_validation_error() {
+        for cursor in ["", "not base64!", &URL_SAFE_NO_PAD.encode("{\"key\":1}")] {
+            assert!(matches!(
+                CustomerCursor::decode(cursor),
+                Err(MasterDataError::ValidationError { field, .. }) if field == "cursor"
+            ));
+        }
+    }
+
+    #[test]
+    fn test_sort_fields_without_a_column_sort_by_legal_name() {
+        assert_eq!(CustomerSortKey::of(Some(&CustomerSortField::TotalRevenue)), CustomerSortKey::LegalName);
+        assert_eq!(CustomerSortKey::of(Some(&CustomerSortField::ModifiedAt)).column(), "updated_at");
+        assert_eq!(CustomerSortKey::of(None), CustomerSortKey::LegalName);
+    }
+}
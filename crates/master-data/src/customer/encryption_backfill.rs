@@ -0,0 +1,419 @@
+//! # Customer Encryption Backfill
+//!
+//! Seals the sensitive customer columns ([`SENSITIVE_FIELDS`]) of rows written
+//! before encryption was enabled, and re-seals rows sealed with a retired key.
+//! Customers are walked in id order, one batch at a time; after every batch
+//! the last id is saved, so a restarted run resumes where the previous one
+//! stopped. Each current key gets its own run (`customer_fields:<key id>`), so
+//! making a new key current starts a fresh pass that re-encrypts everything.
+//!
+//! A row changed by a user between reading and writing is left alone: the
+//! user's write already sealed it with the current key.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::{PgPool, Row};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use super::field_encryption::{replace_blind_indexes, CustomerFieldCipher, StoredField, SENSITIVE_FIELDS};
+use crate::error::Result;
+
+/// Progress of one backfill run
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BackfillProgress {
+    pub job: String,
+    /// Last customer processed; the next batch starts after it
+    pub last_customer_id: Option<Uuid>,
+    pub rows_scanned: i64,
+    pub rows_encrypted: i64,
+    pub started_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+/// A customer's sensitive columns as stored
+#[derive(Debug, Clone)]
+pub struct BackfillRow {
+    pub customer_id: Uuid,
+    pub tenant_id: Uuid,
+    /// Column values by field name, in [`SENSITIVE_FIELDS`] order
+    pub fields: Vec<(String, Option<Value>)>,
+}
+
+/// A column sealed by the backfill
+#[derive(Debug, Clone)]
+pub struct SealedColumn {
+    pub field: String,
+    /// Value read, the write is skipped unless the column still holds it
+    pub previous: Option<Value>,
+    pub sealed: Value,
+    pub blind_indexes: Vec<String>,
+}
+
+#[async_trait]
+pub trait BackfillStore: Send + Sync {
+    async fn progress(&self, job: &str) -> Result<Option<BackfillProgress>>;
+    async fn save_progress(&self, progress: &BackfillProgress) -> Result<()>;
+    /// Customers of every tenant with an id above `after`, in id order
+    async fn rows_after(&self, after: Option<Uuid>, limit: i64) -> Result<Vec<BackfillRow>>;
+    /// Write the sealed columns of one customer; false if the row changed meanwhile
+    async fn write_sealed(&self, row: &BackfillRow, columns: &[SealedColumn]) -> Result<bool>;
+}
+
+/// Backfill state in `customer_encryption_backfill`, customers in the main schema
+pub struct PostgresBackfillStore {
+    pool: PgPool,
+}
+
+impl PostgresBackfillStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl BackfillStore for PostgresBackfillStore {
+    async fn progress(&self, job: &str) -> Result<Option<BackfillProgress>> {
+        let row = sqlx::query(
+            "SELECT job, last_customer_id, rows_scanned, rows_encrypted, started_at, updated_at, completed_at \
+             FROM customer_encryption_backfill WHERE job = $1",
+        )
+        .bind(job)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.map(|row| -> Result<BackfillProgress> {
+            Ok(BackfillProgress {
+                job: row.try_get("job")?,
+                last_customer_id: row.try_get("last_customer_id")?,
+                rows_scanned: row.try_get("rows_scanned")?,
+                rows_encrypted: row.try_get("rows_encrypted")?,
+                started_at: row.try_get("started_at")?,
+                updated_at: row.try_get("updated_at")?,
+                completed_at: row.try_get("completed_at")?,
+            })
+        })
+        .transpose()
+    }
+
+    async fn save_progress(&self, progress: &BackfillProgress) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO customer_encryption_backfill \
+                 (job, last_customer_id, rows_scanned, rows_encrypted, started_at, updated_at, completed_at) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7) \
+             ON CONFLICT (job) DO UPDATE SET \
+                 last_customer_id = EXCLUDED.last_customer_id, rows_scanned = EXCLUDED.rows_scanned, \
+                 rows_encrypted = EXCLUDED.rows_encrypted, updated_at = EXCLUDED.updated_at, \
+                 completed_at = EXCLUDED.completed_at",
+        )
+        .bind(&progress.job)
+        .bind(progress.last_customer_id)
+        .bind(progress.rows_scanned)
+        .bind(progress.rows_encrypted)
+        .bind(progress.started_at)
+        .bind(progress.updated_at)
+        .bind(progress.completed_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn rows_after(&self, after: Option<Uuid>, limit: i64) -> Result<Vec<BackfillRow>> {
+        let rows = sqlx::query(
+            "SELECT id, tenant_id, tax_numbers, bank_accounts FROM customers \
+             WHERE ($1::uuid IS NULL OR id > $1) ORDER BY id LIMIT $2",
+        )
+        .bind(after)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| -> Result<BackfillRow> {
+                let fields = SENSITIVE_FIELDS
+                    .iter()
+                    .map(|field| -> Result<(String, Option<Value>)> {
+                        Ok((field.to_string(), row.try_get(*field)?))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(BackfillRow {
+                    customer_id: row.try_get("id")?,
+                    tenant_id: row.try_get("tenant_id")?,
+                    fields,
+                })
+            })
+            .collect()
+    }
+
+    async fn write_sealed(&self, row: &BackfillRow, columns: &[SealedColumn]) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+        for column in columns {
+            // Only the fixed column names of SENSITIVE_FIELDS reach this query
+            let sql = format!(
+                "UPDATE customers SET {0} = $1 WHERE id = $2 AND {0} IS NOT DISTINCT FROM $3",
+                column.field
+            );
+            let updated = sqlx::query(&sql)
+                .bind(&column.sealed)
+                .bind(row.customer_id)
+                .bind(&column.previous)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+            if updated == 0 {
+                tx.rollback().await?;
+                return Ok(false);
+            }
+            replace_blind_indexes(&mut tx, row.tenant_id, row.customer_id, &column.field, &column.blind_indexes).await?;
+        }
+        tx.commit().await?;
+        Ok(true)
+    }
+}
+
+/// Nothing worth sealing: `NULL`, `[]` or `{}`
+fn is_empty(value: &Value) -> bool {
+    match value {
+        Value::Null => true,
+        Value::Array(items) => items.is_empty(),
+        Value::Object(entries) => entries.is_empty(),
+        _ => false,
+    }
+}
+
+/// Encrypts existing customer rows in batches
+pub struct CustomerEncryptionBackfill {
+    store: Arc<dyn BackfillStore>,
+    cipher: Arc<CustomerFieldCipher>,
+    batch_size: i64,
+}
+
+impl CustomerEncryptionBackfill {
+    pub fn new(store: Arc<dyn BackfillStore>, cipher: Arc<CustomerFieldCipher>, batch_size: i64) -> Self {
+        Self {
+            store,
+            cipher,
+            batch_size: batch_size.max(1),
+        }
+    }
+
+    /// Run name for the current key
+    pub fn job(&self) -> String {
+        format!("customer_fields:{}", self.cipher.current_key_id())
+    }
+
+    /// Progress of the run for the current key, if it started
+    pub async fn progress(&self) -> Result<Option<BackfillProgress>> {
+        self.store.progress(&self.job()).await
+    }
+
+    /// Seal the columns of one row that need it
+    async fn seal_row(&self, row: &BackfillRow) -> Result<Vec<SealedColumn>> {
+        let mut columns = Vec::new();
+        for (field, value) in &row.fields {
+            let stored = StoredField::from_json(field, value.clone())?;
+            if !self.cipher.needs_sealing(&stored) {
+                continue;
+            }
+            let plain = self.cipher.open(row.tenant_id, field, &stored).await?;
+            if is_empty(&plain) {
+                continue;
+            }
+            columns.push(SealedColumn {
+                field: field.clone(),
+                previous: value.clone(),
+                sealed: self.cipher.seal(row.tenant_id, field, &plain).await?.to_json(),
+                blind_indexes: self.cipher.blind_indexes(row.tenant_id, field, &plain),
+            });
+        }
+        Ok(columns)
+    }
+
+    /// Process the next batch and save the progress; completed runs do nothing
+    pub async fn run_batch(&self, now: DateTime<Utc>) -> Result<BackfillProgress> {
+        let job = self.job();
+        let mut progress = self.store.progress(&job).await?.unwrap_or(BackfillProgress {
+            job,
+            last_customer_id: None,
+            rows_scanned: 0,
+            rows_encrypted: 0,
+            started_at: now,
+            updated_at: now,
+            completed_at: None,
+        });
+        if progress.completed_at.is_some() {
+            return Ok(progress);
+        }
+
+        let rows = self.store.rows_after(progress.last_customer_id, self.batch_size).await?;
+        for row in &rows {
+            let columns = self.seal_row(row).await?;
+            if !columns.is_empty() && self.store.write_sealed(row, &columns).await? {
+                progress.rows_encrypted += 1;
+            }
+            progress.rows_scanned += 1;
+            progress.last_customer_id = Some(row.customer_id);
+        }
+
+        progress.updated_at = now;
+        if (rows.len() as i64) < self.batch_size {
+            progress.completed_at = Some(now);
+        }
+        self.store.save_progress(&progress).await?;
+        Ok(progress)
+    }
+
+    /// Run batches until the run completes or `max_batches` were processed
+    pub async fn run(&self, max_batches: usize) -> Result<BackfillProgress> {
+        let mut progress = self.run_batch(Utc::now()).await?;
+        for _ in 1..max_batches {
+            if progress.completed_at.is_some() {
+                break;
+            }
+            progress = self.run_batch(Utc::now()).await?;
+        }
+        Ok(progress)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::customer::field_encryption::tests::cipher;
+    use crate::customer::field_encryption::{BANK_ACCOUNTS, TAX_NUMBERS};
+    use serde_json::json;
+    use std::collections::{BTreeMap, HashMap};
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct MemoryStore {
+        rows: Mutex<BTreeMap<Uuid, BackfillRow>>,
+        progress: Mutex<HashMap<String, BackfillProgress>>,
+        indexes: Mutex<HashMap<(Uuid, String), Vec<String>>>,
+        writes: Mutex<usize>,
+    }
+
+    impl MemoryStore {
+        fn insert(&self, tenant_id: Uuid, tax_numbers: Option<Value>, bank_accounts: Option<Value>) -> Uuid {
+            let customer_id = Uuid::new_v4();
+            self.rows.lock().unwrap().insert(
+                customer_id,
+                BackfillRow {
+                    customer_id,
+                    tenant_id,
+                    fields: vec![(TAX_NUMBERS.to_string(), tax_numbers), (BANK_ACCOUNTS.to_string(), bank_accounts)],
+                },
+            );
+            customer_id
+        }
+
+        fn stored(&self, customer_id: Uuid, field: &str) -> StoredField {
+            let rows = self.rows.lock().unwrap();
+            let value = rows[&customer_id].fields.iter().find(|(f, _)| f == field).unwrap().1.clone();
+            StoredField::from_json(field, value).unwrap()
+        }
+    }
+
+    #[async_trait]
+    impl BackfillStore for MemoryStore {
+        async fn progress(&self, job: &str) -> Result<Option<BackfillProgress>> {
+            Ok(self.progress.lock().unwrap().get(job).cloned())
+        }
+
+        async fn save_progress(&self, progress: &BackfillProgress) -> Result<()> {
+            self.progress.lock().unwrap().insert(progress.job.clone(), progress.clone());
+            Ok(())
+        }
+
+        async fn rows_after(&self, after: Option<Uuid>, limit: i64) -> Result<Vec<BackfillRow>> {
+            let rows = self.rows.lock().unwrap();
+            Ok(rows
+                .values()
+                .filter(|row| after.is_none_or(|after| row.customer_id > after))
+                .take(limit as usize)
+                .cloned()
+                .collect())
+        }
+
+        async fn write_sealed(&self, row: &BackfillRow, columns: &[SealedColumn]) -> Result<bool> {
+            let mut rows = self.rows.lock().unwrap();
+            let stored = rows.get_mut(&row.customer_id).unwrap();
+            for column in columns {
+                let slot = stored.fields.iter_mut().find(|(field, _)| *field == column.field).unwrap();
+                if slot.1 != column.previous {
+                    return Ok(false);
+                }
+                slot.1 = Some(column.sealed.clone());
+                self.indexes
+                    .lock()
+                    .unwrap()
+                    .insert((row.customer_id, column.field.clone()), column.blind_indexes.clone());
+            }
+            *self.writes.lock().unwrap() += 1;
+            Ok(true)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_backfill_resumes_after_interruption() {
+        let store = Arc::new(MemoryStore::default());
+        let tenant_id = Uuid::new_v4();
+        let ids: Vec<Uuid> = (0..5)
+            .map(|i| store.insert(tenant_id, Some(json!({"VAT": format!("DE00000000{}", i)})), None))
+            .collect();
+
+        let first = CustomerEncryptionBackfill::new(store.clone(), Arc::new(cipher(&[("v1", 1)], "v1")), 2);
+        let progress = first.run_batch(Utc::now()).await.unwrap();
+        assert_eq!((progress.rows_scanned, progress.completed_at), (2, None));
+        let plaintext_left = ids
+            .iter()
+            .filter(|id| matches!(store.stored(**id, TAX_NUMBERS), StoredField::Plaintext(_)))
+            .count();
+        assert_eq!(plaintext_left, 3, "rows not reached yet stay plaintext");
+
+        // A restarted process picks up after the last saved customer
+        let resumed = CustomerEncryptionBackfill::new(store.clone(), Arc::new(cipher(&[("v1", 1)], "v1")), 2);
+        let progress = resumed.run(10).await.unwrap();
+        assert!(progress.completed_at.is_some());
+        assert_eq!((progress.rows_scanned, progress.rows_encrypted), (5, 5));
+        assert_eq!(*store.writes.lock().unwrap(), 5);
+
+        for id in &ids {
+            assert_eq!(store.stored(*id, TAX_NUMBERS).key_id(), Some("v1"));
+            assert_eq!(store.indexes.lock().unwrap()[&(*id, TAX_NUMBERS.to_string())].len(), 1);
+        }
+
+        // Completed runs are not repeated
+        resumed.run(10).await.unwrap();
+        assert_eq!(*store.writes.lock().unwrap(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_new_current_key_re_encrypts() {
+        let store = Arc::new(MemoryStore::default());
+        let tenant_id = Uuid::new_v4();
+        let numbers = json!({"VAT": "DE123456789"});
+        let id = store.insert(tenant_id, Some(numbers.clone()), None);
+        let empty = store.insert(tenant_id, None, Some(json!([])));
+
+        let progress = CustomerEncryptionBackfill::new(store.clone(), Arc::new(cipher(&[("v1", 1)], "v1")), 10)
+            .run(10)
+            .await
+            .unwrap();
+        assert_eq!(progress.rows_encrypted, 1, "the empty row has nothing to seal");
+        assert!(matches!(store.stored(empty, BANK_ACCOUNTS), StoredField::Plaintext(_)));
+
+        let rotated = Arc::new(cipher(&[("v1", 1), ("v2", 2)], "v2"));
+        let backfill = CustomerEncryptionBackfill::new(store.clone(), rotated.clone(), 10);
+        assert_eq!(backfill.job(), "customer_fields:v2");
+        let progress = backfill.run(10).await.unwrap();
+        assert_eq!(progress.rows_encrypted, 1);
+
+        let stored = store.stored(id, TAX_NUMBERS);
+        assert_eq!(stored.key_id(), Some("v2"));
+        assert_eq!(rotated.open(tenant_id, TAX_NUMBERS, &stored).await.unwrap(), numbers);
+    }
+}
@@ -6,7 +6,7 @@
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
 use serde_json;
-use sqlx::{PgPool, Row};
+use sqlx::{PgConnection, PgPool, Row};
 use std::collections::HashMap;
 use uuid::Uuid;
 
@@ -92,12 +92,13 @@ impl PostgresCustomerEventStore {
             tenant_context,
         }
     }
-}
 
-#[async_trait]
-impl CustomerEventStore for PostgresCustomerEventStore {
-    async fn append_events(
+    /// Append events as [`CustomerEventStore::append_events`] does, on a
+    /// connection whose transaction the caller commits together with its own
+    /// changes
+    pub async fn append_events_on(
         &self,
+        conn: &mut PgConnection,
         aggregate_id: Uuid,
         events: Vec<CustomerEvent>,
         expected_version: Option<i64>,
@@ -107,15 +108,13 @@ impl CustomerEventStore for PostgresCustomerEventStore {
             return Ok(0);
         }
 
-        let mut tx = self.pool.begin().await?;
-
         // Get current version with row lock (first lock existing records, then get max)
         sqlx::query(
             "SELECT event_id FROM customer_events WHERE aggregate_id = $1 AND tenant_id = $2 FOR UPDATE",
         )
         .bind(aggregate_id)
         .bind(self.tenant_context.tenant_id.0)
-        .fetch_all(&mut *tx)
+        .fetch_all(&mut *conn)
         .await?;
 
         let current_version: i64 = sqlx::query_scalar::<_, i64>(
@@ -124,7 +123,7 @@ impl CustomerEventStore for PostgresCustomerEventStore {
         )
         .bind(aggregate_id)
         .bind(self.tenant_context.tenant_id.0)
-        .fetch_one(&mut *tx)
+        .fetch_one(&mut *conn)
         .await?;
 
         // Check optimistic concurrency control
@@ -201,13 +200,34 @@ impl CustomerEventStore for PostgresCustomerEventStore {
             .bind(occurred_at)
             .bind(recorded_at)
             .bind(uid)
-            .execute(&mut *tx)
+            .execute(&mut *conn)
             .await?;
         }
 
+        Ok(next_version)
+    }
+}
+
+#[async_trait]
+impl CustomerEventStore for PostgresCustomerEventStore {
+    async fn append_events(
+        &self,
+        aggregate_id: Uuid,
+        events: Vec<CustomerEvent>,
+        expected_version: Option<i64>,
+        user_id: Option<Uuid>,
+    ) -> Result<i64> {
+        if events.is_empty() {
+            return Ok(0);
+        }
+
+        let mut tx = self.pool.begin().await?;
+        let version = self
+            .append_events_on(&mut tx, aggregate_id, events, expected_version, user_id)
+            .await?;
         tx.commit().await?;
 
-        Ok(next_version)
+        Ok(version)
     }
 
     async fn load_events(&self, aggregate_id: Uuid) -> Result<Vec<CustomerEventWithMetadata>> {
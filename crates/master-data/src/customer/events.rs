@@ -7,6 +7,7 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use crate::customer::aggregate::{MergeField, MergeReassignments};
 use crate::customer::model::*;
 use crate::types::*;
 
@@ -212,6 +213,23 @@ pub enum CustomerEvent {
         assessed_by: Uuid,
         assessed_at: DateTime<Utc>,
     },
+
+    /// A duplicate customer was merged into this one, with the values the
+    /// merge strategy chose
+    Merged {
+        customer_id: Uuid,
+        source_customer_id: Uuid,
+        legal_name: String,
+        customer_type: CustomerType,
+        currency_code: String,
+        credit_limit: Option<rust_decimal::Decimal>,
+        payment_terms: Option<PaymentTerms>,
+        tax_exempt: bool,
+        fields_from_source: Vec<MergeField>,
+        reassigned: MergeReassignments,
+        merged_by: Uuid,
+        merged_at: DateTime<Utc>,
+    },
 }
 
 /// Event metadata for audit and tracking
@@ -260,6 +278,7 @@ impl CustomerEvent {
             CustomerEvent::HierarchyChanged { customer_id, .. } => *customer_id,
             CustomerEvent::SegmentationUpdated { customer_id, .. } => *customer_id,
             CustomerEvent::RiskRatingUpdated { customer_id, .. } => *customer_id,
+            CustomerEvent::Merged { customer_id, .. } => *customer_id,
         }
     }
 
@@ -285,6 +304,7 @@ impl CustomerEvent {
             CustomerEvent::HierarchyChanged { changed_at, .. } => *changed_at,
             CustomerEvent::SegmentationUpdated { updated_at, .. } => *updated_at,
             CustomerEvent::RiskRatingUpdated { assessed_at, .. } => *assessed_at,
+            CustomerEvent::Merged { merged_at, .. } => *merged_at,
         }
     }
 
@@ -310,6 +330,7 @@ impl CustomerEvent {
             CustomerEvent::HierarchyChanged { .. } => "hierarchy_changed",
             CustomerEvent::SegmentationUpdated { .. } => "segmentation_updated",
             CustomerEvent::RiskRatingUpdated { .. } => "risk_rating_updated",
+            CustomerEvent::Merged { .. } => "customer_merged",
         }
     }
 
@@ -323,6 +344,7 @@ impl CustomerEvent {
                 | CustomerEvent::ComplianceStatusChanged { .. }
                 | CustomerEvent::CustomerSoftDeleted { .. }
                 | CustomerEvent::RiskRatingUpdated { .. }
+                | CustomerEvent::Merged { .. }
         )
     }
 }
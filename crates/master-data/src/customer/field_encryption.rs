@@ -0,0 +1,431 @@
+//! # Customer Field Encryption
+//!
+//! `tax_numbers` and `bank_accounts` are stored encrypted in their JSONB
+//! columns. A sealed column holds an envelope instead of the plain value:
+//!
+//! ```json
+//! {"$encrypted": { ...EncryptedField... }, "masked": {"VAT": "DE*****6789"}}
+//! ```
+//!
+//! - `masked` is the value as masked API responses show it, so they never
+//!   need the keys.
+//! - Every identifier in a field (each tax number, each IBAN) also gets a
+//!   blind index in `customer_blind_indexes`: an HMAC of its normalized form,
+//!   keyed per deployment and scoped to tenant and field. Exact-match lookups
+//!   compare blind indexes, so searching never decrypts.
+//! - Rows written before encryption was enabled stay plaintext until the
+//!   backfill ([`super::encryption_backfill`]) seals them; reads and lookups
+//!   handle both forms in the meantime.
+
+use hmac::{Hmac, Mac};
+use serde_json::{json, Map, Value};
+use sha2::Sha256;
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::error::{MasterDataError, Result};
+use crate::security::encryption::{
+    ComplianceLevel, DataClassification, EncryptedField, EncryptionContext, EncryptionOperation, FieldEncryption,
+};
+use crate::security::VersionedFieldEncryption;
+use crate::types::BankAccount;
+
+/// Tax type to tax number, e.g. `{"VAT": "DE123456789"}`
+pub const TAX_NUMBERS: &str = "tax_numbers";
+/// List of [`BankAccount`]s
+pub const BANK_ACCOUNTS: &str = "bank_accounts";
+/// Every customer column stored encrypted
+pub const SENSITIVE_FIELDS: [&str; 2] = [TAX_NUMBERS, BANK_ACCOUNTS];
+
+const ENCRYPTED: &str = "$encrypted";
+const MASKED: &str = "masked";
+
+/// A sensitive column as stored
+#[derive(Debug, Clone)]
+pub enum StoredField {
+    /// Written before encryption was enabled and not yet backfilled
+    Plaintext(Value),
+    Encrypted { sealed: EncryptedField, masked: Value },
+}
+
+impl StoredField {
+    /// Parse a column value; `NULL` is an empty plaintext value
+    pub fn from_json(field: &str, value: Option<Value>) -> Result<Self> {
+        let value = value.unwrap_or(Value::Null);
+        let Some(envelope) = value.as_object().filter(|object| object.contains_key(ENCRYPTED)) else {
+            return Ok(StoredField::Plaintext(value));
+        };
+        let sealed = serde_json::from_value(envelope[ENCRYPTED].clone()).map_err(|e| {
+            MasterDataError::FieldDecryptionFailed {
+                field: field.to_string(),
+                key_id: "unknown".to_string(),
+                reason: format!("malformed envelope: {}", e),
+            }
+        })?;
+        let masked = envelope.get(MASKED).cloned().unwrap_or(Value::Null);
+        Ok(StoredField::Encrypted { sealed, masked })
+    }
+
+    pub fn to_json(&self) -> Value {
+        match self {
+            StoredField::Plaintext(value) => value.clone(),
+            StoredField::Encrypted { sealed, masked } => json!({ ENCRYPTED: sealed, MASKED: masked }),
+        }
+    }
+
+    /// Key that sealed the value, `None` while plaintext
+    pub fn key_id(&self) -> Option<&str> {
+        match self {
+            StoredField::Plaintext(_) => None,
+            StoredField::Encrypted { sealed, .. } => Some(&sealed.key_id),
+        }
+    }
+
+    /// The value as masked responses show it, without decrypting
+    pub fn masked(&self, field: &str) -> Value {
+        match self {
+            StoredField::Plaintext(value) => mask_field(field, value),
+            StoredField::Encrypted { masked, .. } => masked.clone(),
+        }
+    }
+}
+
+/// Uppercase without separators, so `de 8937-0400` and `DE89370400` match
+pub fn normalize_identifier(raw: &str) -> String {
+    raw.chars()
+        .filter(|c| !c.is_whitespace() && !matches!(c, '-' | '.' | '/'))
+        .flat_map(char::to_uppercase)
+        .collect()
+}
+
+/// Keep the country prefix and the last digits of long identifiers
+pub fn mask_identifier(raw: &str) -> String {
+    let chars: Vec<char> = normalize_identifier(raw).chars().collect();
+    let len = chars.len();
+    let prefix = if len >= 8 { 2 } else { 0 };
+    let suffix = match len {
+        10.. => 4,
+        6..=9 => 2,
+        _ => 0,
+    };
+    chars
+        .iter()
+        .enumerate()
+        .map(|(i, c)| if i < prefix || i >= len - suffix { *c } else { '*' })
+        .collect()
+}
+
+/// Masked form of a plain field value
+pub fn mask_field(field: &str, value: &Value) -> Value {
+    match (field, value) {
+        (TAX_NUMBERS, Value::Object(numbers)) => Value::Object(
+            numbers
+                .iter()
+                .map(|(kind, number)| (kind.clone(), json!(mask_identifier(number.as_str().unwrap_or_default()))))
+                .collect::<Map<_, _>>(),
+        ),
+        (BANK_ACCOUNTS, Value::Array(_)) => {
+            let accounts: Vec<BankAccount> = serde_json::from_value(value.clone()).unwrap_or_default();
+            let masked: Vec<BankAccount> = accounts
+                .into_iter()
+                .map(|account| BankAccount {
+                    iban: mask_identifier(&account.iban),
+                    bic: account.bic,
+                    account_holder: None,
+                })
+                .collect();
+            serde_json::to_value(masked).unwrap_or(Value::Null)
+        }
+        _ => value.clone(),
+    }
+}
+
+/// Normalized identifiers in a plain field value: tax numbers or IBANs
+pub fn identifiers(field: &str, value: &Value) -> Vec<String> {
+    let raw: Vec<String> = match (field, value) {
+        (TAX_NUMBERS, Value::Object(numbers)) => numbers
+            .values()
+            .filter_map(|number| number.as_str().map(str::to_string))
+            .collect(),
+        (BANK_ACCOUNTS, Value::Array(_)) => serde_json::from_value::<Vec<BankAccount>>(value.clone())
+            .unwrap_or_default()
+            .into_iter()
+            .map(|account| account.iban)
+            .collect(),
+        _ => Vec::new(),
+    };
+    raw.iter()
+        .map(|identifier| normalize_identifier(identifier))
+        .filter(|identifier| !identifier.is_empty())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+/// Whether a plaintext row contains the identifier, for rows not yet backfilled
+pub fn plaintext_matches(field: &str, value: &Value, identifier: &str) -> bool {
+    let wanted = normalize_identifier(identifier);
+    identifiers(field, value).contains(&wanted)
+}
+
+/// HMAC-SHA256 blind indexes of normalized identifiers
+#[derive(Clone)]
+pub struct BlindIndexer {
+    key: Vec<u8>,
+}
+
+impl BlindIndexer {
+    pub fn new(key: &[u8]) -> Result<Self> {
+        if key.len() < 32 {
+            return Err(MasterDataError::ValidationError {
+                field: "blind_index_key".to_string(),
+                message: "Blind index key must be at least 32 bytes".to_string(),
+            });
+        }
+        Ok(Self { key: key.to_vec() })
+    }
+
+    /// Index key derived from an encryption key, so the two never coincide
+    pub fn derived_from(encryption_key: &[u8]) -> Result<Self> {
+        let mut mac =
+            <Hmac<Sha256> as Mac>::new_from_slice(encryption_key).expect("HMAC accepts keys of any length");
+        mac.update(b"customer-blind-index");
+        Self::new(&mac.finalize().into_bytes())
+    }
+
+    /// Hex digest; equal for equal normalized identifiers of the same tenant and field
+    pub fn index(&self, tenant_id: Uuid, field: &str, identifier: &str) -> String {
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&self.key).expect("HMAC accepts keys of any length");
+        mac.update(tenant_id.as_bytes());
+        mac.update(field.as_bytes());
+        mac.update(&[0]);
+        mac.update(normalize_identifier(identifier).as_bytes());
+        mac.finalize()
+            .into_bytes()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+}
+
+/// Sensitive identifiers of a customer as masked responses show them
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+pub struct MaskedFinancialIdentifiers {
+    pub tax_numbers: std::collections::HashMap<String, String>,
+    pub bank_accounts: Vec<BankAccount>,
+}
+
+/// Seals and opens the sensitive customer columns of every tenant
+pub struct CustomerFieldCipher {
+    encryption: Arc<VersionedFieldEncryption>,
+    blind_index: BlindIndexer,
+}
+
+impl CustomerFieldCipher {
+    pub fn new(encryption: Arc<VersionedFieldEncryption>, blind_index: BlindIndexer) -> Self {
+        Self {
+            encryption,
+            blind_index,
+        }
+    }
+
+    pub fn current_key_id(&self) -> &str {
+        self.encryption.current_key_id()
+    }
+
+    fn context(tenant_id: Uuid, operation: EncryptionOperation) -> EncryptionContext {
+        EncryptionContext {
+            tenant_id,
+            // Stored values are readable by every user of the tenant
+            user_id: Uuid::nil(),
+            operation,
+            compliance_level: ComplianceLevel::High,
+            data_classification: DataClassification::Restricted,
+        }
+    }
+
+    /// Encrypt a plain value with the current key
+    pub async fn seal(&self, tenant_id: Uuid, field: &str, value: &Value) -> Result<StoredField> {
+        let sealed = self
+            .encryption
+            .encrypt_field(&value.to_string(), field, &Self::context(tenant_id, EncryptionOperation::Update))
+            .await?;
+        Ok(StoredField::Encrypted {
+            sealed,
+            masked: mask_field(field, value),
+        })
+    }
+
+    /// The plain value; plaintext rows pass through unchanged
+    pub async fn open(&self, tenant_id: Uuid, field: &str, stored: &StoredField) -> Result<Value> {
+        match stored {
+            StoredField::Plaintext(value) => Ok(value.clone()),
+            StoredField::Encrypted { sealed, .. } => {
+                let plain = self
+                    .encryption
+                    .decrypt_field(sealed, &Self::context(tenant_id, EncryptionOperation::Read))
+                    .await?;
+                serde_json::from_str(&plain).map_err(|e| MasterDataError::FieldDecryptionFailed {
+                    field: field.to_string(),
+                    key_id: sealed.key_id.clone(),
+                    reason: format!("decrypted value is not JSON: {}", e),
+                })
+            }
+        }
+    }
+
+    /// Plaintext, or sealed with a key other than the current one
+    pub fn needs_sealing(&self, stored: &StoredField) -> bool {
+        stored.key_id() != Some(self.current_key_id())
+    }
+
+    /// Blind indexes of every identifier in a plain value
+    pub fn blind_indexes(&self, tenant_id: Uuid, field: &str, value: &Value) -> Vec<String> {
+        identifiers(field, value)
+            .iter()
+            .map(|identifier| self.blind_index.index(tenant_id, field, identifier))
+            .collect()
+    }
+
+    /// Blind index to look an identifier up by
+    pub fn lookup_index(&self, tenant_id: Uuid, field: &str, identifier: &str) -> String {
+        self.blind_index.index(tenant_id, field, identifier)
+    }
+}
+
+/// Replace the blind indexes of one customer field
+pub(crate) async fn replace_blind_indexes(
+    conn: &mut sqlx::PgConnection,
+    tenant_id: Uuid,
+    customer_id: Uuid,
+    field: &str,
+    indexes: &[String],
+) -> Result<()> {
+    sqlx::query("DELETE FROM customer_blind_indexes WHERE customer_id = $1 AND field = $2")
+        .bind(customer_id)
+        .bind(field)
+        .execute(&mut *conn)
+        .await?;
+    for index in indexes {
+        sqlx::query(
+            "INSERT INTO customer_blind_indexes (tenant_id, customer_id, field, blind_index) \
+             VALUES ($1, $2, $3, $4) ON CONFLICT DO NOTHING",
+        )
+        .bind(tenant_id)
+        .bind(customer_id)
+        .bind(field)
+        .bind(index)
+        .execute(&mut *conn)
+        .await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::security::KeyRing;
+
+    pub(crate) fn cipher(keys: &[(&str, u8)], current: &str) -> CustomerFieldCipher {
+        let ring = KeyRing::new(keys.iter().map(|(id, byte)| (id.to_string(), vec![*byte; 32])).collect(), current)
+            .unwrap();
+        CustomerFieldCipher::new(
+            Arc::new(VersionedFieldEncryption::new(ring)),
+            BlindIndexer::new(&[7; 32]).unwrap(),
+        )
+    }
+
+    fn accounts() -> Value {
+        json!([{"iban": "DE89 3704 0044 0532 0130 00", "bic": "COBADEFFXXX", "account_holder": "Acme GmbH"}])
+    }
+
+    #[tokio::test]
+    async fn test_round_trip_through_stored_envelope() {
+        let cipher = cipher(&[("v1", 1)], "v1");
+        let tenant_id = Uuid::new_v4();
+        let numbers = json!({"VAT": "DE123456789", "EORI": "DE9876543210000"});
+
+        let sealed = cipher.seal(tenant_id, TAX_NUMBERS, &numbers).await.unwrap();
+        let stored = sealed.to_json();
+        assert!(!stored.to_string().contains("DE123456789"), "no plaintext in the column");
+
+        let parsed = StoredField::from_json(TAX_NUMBERS, Some(stored)).unwrap();
+        assert_eq!(parsed.key_id(), Some("v1"));
+        assert_eq!(cipher.open(tenant_id, TAX_NUMBERS, &parsed).await.unwrap(), numbers);
+
+        let accounts = accounts();
+        let sealed = cipher.seal(tenant_id, BANK_ACCOUNTS, &accounts).await.unwrap();
+        assert_eq!(cipher.open(tenant_id, BANK_ACCOUNTS, &sealed).await.unwrap(), accounts);
+    }
+
+    #[tokio::test]
+    async fn test_masked_values_need_no_keys() {
+        let cipher = cipher(&[("v1", 1)], "v1");
+        let sealed = cipher.seal(Uuid::new_v4(), BANK_ACCOUNTS, &accounts()).await.unwrap();
+        let parsed = StoredField::from_json(BANK_ACCOUNTS, Some(sealed.to_json())).unwrap();
+
+        let masked: Vec<BankAccount> = serde_json::from_value(parsed.masked(BANK_ACCOUNTS)).unwrap();
+        assert_eq!(masked[0].iban, "DE****************3000");
+        assert_eq!(masked[0].bic.as_deref(), Some("COBADEFFXXX"));
+        assert_eq!(masked[0].account_holder, None);
+
+        let plaintext = StoredField::Plaintext(json!({"VAT": "DE123456789"}));
+        assert_eq!(plaintext.masked(TAX_NUMBERS), json!({"VAT": "DE*****6789"}));
+        assert_eq!(mask_identifier("1234"), "****");
+    }
+
+    #[test]
+    fn test_blind_index_lookup_matches_normalized_identifiers() {
+        let cipher = cipher(&[("v1", 1)], "v1");
+        let tenant_id = Uuid::new_v4();
+
+        let stored = cipher.blind_indexes(tenant_id, BANK_ACCOUNTS, &accounts());
+        assert_eq!(stored.len(), 1);
+        assert_eq!(cipher.lookup_index(tenant_id, BANK_ACCOUNTS, "de89370400440532013000"), stored[0]);
+        assert_eq!(cipher.lookup_index(tenant_id, BANK_ACCOUNTS, "DE89-3704-0044-0532-0130-00"), stored[0]);
+        assert_ne!(cipher.lookup_index(tenant_id, BANK_ACCOUNTS, "DE89370400440532013001"), stored[0]);
+        assert_ne!(cipher.lookup_index(Uuid::new_v4(), BANK_ACCOUNTS, "DE89370400440532013000"), stored[0]);
+        assert_ne!(cipher.lookup_index(tenant_id, TAX_NUMBERS, "DE89370400440532013000"), stored[0]);
+    }
+
+    #[tokio::test]
+    async fn test_plaintext_rows_mid_backfill() {
+        let cipher = cipher(&[("v1", 1), ("v2", 2)], "v2");
+        let tenant_id = Uuid::new_v4();
+        let legacy = json!({"VAT": "GB 123 4567 89"});
+
+        let stored = StoredField::from_json(TAX_NUMBERS, Some(legacy.clone())).unwrap();
+        assert!(matches!(stored, StoredField::Plaintext(_)));
+        assert!(cipher.needs_sealing(&stored));
+        assert_eq!(cipher.open(tenant_id, TAX_NUMBERS, &stored).await.unwrap(), legacy);
+        assert!(plaintext_matches(TAX_NUMBERS, &legacy, "gb123456789"));
+        assert!(!plaintext_matches(TAX_NUMBERS, &legacy, "GB123456780"));
+
+        let empty = StoredField::from_json(TAX_NUMBERS, None).unwrap();
+        assert_eq!(cipher.open(tenant_id, TAX_NUMBERS, &empty).await.unwrap(), Value::Null);
+
+        let old_key = super::tests::cipher(&[("v1", 1)], "v1").seal(tenant_id, TAX_NUMBERS, &legacy).await.unwrap();
+        assert!(cipher.needs_sealing(&old_key), "sealed with a retired key");
+        let current = cipher.seal(tenant_id, TAX_NUMBERS, &legacy).await.unwrap();
+        assert!(!cipher.needs_sealing(&current));
+    }
+
+    #[tokio::test]
+    async fn test_decryption_failure_names_field_and_key() {
+        let tenant_id = Uuid::new_v4();
+        let sealed = cipher(&[("v1", 1)], "v1").seal(tenant_id, TAX_NUMBERS, &json!({"VAT": "X"})).await.unwrap();
+
+        let err = cipher(&[("v2", 2)], "v2").open(tenant_id, TAX_NUMBERS, &sealed).await.unwrap_err();
+        match err {
+            MasterDataError::FieldDecryptionFailed { field, key_id, .. } => {
+                assert_eq!(field, TAX_NUMBERS);
+                assert_eq!(key_id, "v1");
+            }
+            other => panic!("unexpected error: {}", other),
+        }
+
+        let malformed = StoredField::from_json(TAX_NUMBERS, Some(json!({"$encrypted": "garbage"})));
+        assert!(matches!(malformed, Err(MasterDataError::FieldDecryptionFailed { .. })));
+    }
+}
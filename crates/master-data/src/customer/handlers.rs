@@ -57,6 +57,8 @@ pub struct CustomersResponse {
     pub page: u32,
     pub page_size: u32,
     pub total_pages: u32,
+    /// Pass as `cursor` to get the next page; absent on the last one
+    pub next_cursor: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Validate)]
@@ -142,6 +144,7 @@ async fn update_customer(
 
 /// Search customers with advanced filtering
 /// GET /customers?search_term=...&customer_type=...&page=1&page_size=50
+/// GET /customers?search_term=...&cursor=<next_cursor>&page_size=50
 async fn search_customers(
     State(service): State<Arc<dyn CustomerService>>,
     Query(params): Query<CustomerSearchQueryParams>,
@@ -155,6 +158,7 @@ async fn search_customers(
         page: response.page,
         page_size: response.page_size,
         total_pages: response.total_pages,
+        next_cursor: response.next_cursor,
     }))
 }
 
@@ -306,6 +310,8 @@ pub struct CustomerSearchQueryParams {
     // Pagination
     pub page: Option<u32>,
     pub page_size: Option<u32>,
+    /// `next_cursor` of the previous page; `page` is ignored when given
+    pub cursor: Option<String>,
 
     // Sorting
     pub sort_by: Option<String>,
@@ -338,6 +344,7 @@ impl CustomerSearchQueryParams {
             max_annual_revenue: self.max_annual_revenue,
             page: self.page.unwrap_or(1),
             page_size: std::cmp::min(self.page_size.unwrap_or(50), 1000), // Cap at 1000
+            cursor: self.cursor,
             sort_by: self.sort_by,
             sort_order: self.sort_order,
             include_deleted: Some(false), // Default to excluding deleted
@@ -408,6 +415,15 @@ mod tests {
             })
         }
 
+        async fn bulk_create_customers(
+            &self,
+            _requests: Vec<CreateCustomerRequest>,
+            _options: crate::customer::BulkImportOptions,
+            _created_by: Uuid,
+        ) -> Result<crate::customer::BulkImportResult> {
+            unimplemented!()
+        }
+
         async fn update_customer(&self, id: Uuid, request: UpdateCustomerRequest, modified_by: Uuid) -> Result<Customer> {
             use chrono::Utc;
 
@@ -481,6 +497,7 @@ mod tests {
                 page: 1,
                 page_size: 50,
                 total_pages: 0,
+                next_cursor: None,
             })
         }
 
@@ -2,12 +2,23 @@ pub mod model;
 pub mod repository;
 pub mod service;
 pub mod search;
+pub mod cursor;
 pub mod validation;
 pub mod analytics;
 pub mod analytics_engine;
 pub mod events;
 pub mod event_store;
 pub mod aggregate;
+pub mod communication;
+pub mod communication_service;
+pub mod territory;
+pub mod territory_service;
+pub mod retention;
+pub mod field_encryption;
+pub mod encryption_backfill;
+pub mod scoped;
+pub mod csv_schema;
+pub mod bulk_import;
 
 #[cfg(feature = "axum")]
 pub mod handlers;
@@ -24,14 +35,39 @@ pub use model::{
     AcquisitionChannel, ComplianceStatus, KycStatus,
 };
 
-pub use repository::{CustomerRepository, PostgresCustomerRepository};
+pub use repository::{
+    CommunicationRepository, CustomerMergeRepository, CustomerRepository, PostgresCommunicationRepository,
+    PostgresCustomerRepository, PostgresTerritoryRepository, TerritoryRepository,
+};
+pub use scoped::ScopedCustomerRepository;
+pub use cursor::{CustomerCursor, CustomerOrder, CustomerPage, CustomerSortKey};
 pub use service::{CustomerService, DefaultCustomerService};
+pub use communication::{
+    classify_follow_ups, follow_up_digests, CommunicationDirection, CommunicationScope, CommunicationType,
+    CreateCommunicationRequest, CustomerCommunication, FollowUpDigest, FollowUpsDue, UpdateCommunicationRequest,
+};
+pub use communication_service::{CommunicationService, DefaultCommunicationService};
+pub use territory::{
+    plan_reassignment, winning_territory, Assignee, AssigneeMove, AssignmentOverrideRequest, AssignmentRole, AssignmentSource, CustomerAssignment,
+    CustomerOwnership, CustomerProfile, OwnershipChange, PlannedAssignment, ReassignmentPlan, Territory,
+    TerritoryRequest, TerritoryRules, PERMISSION_MANAGE_TERRITORIES,
+};
+pub use territory_service::{DefaultTerritoryService, TerritoryService};
+pub use retention::{CommunicationRetention, DeletedCustomerPurge};
+pub use field_encryption::{BlindIndexer, CustomerFieldCipher, MaskedFinancialIdentifiers, StoredField};
+pub use encryption_backfill::{
+    BackfillProgress, BackfillStore, CustomerEncryptionBackfill, PostgresBackfillStore,
+};
 pub use events::{CustomerEvent, CustomerEventWithMetadata, EventMetadata};
 pub use event_store::{CustomerEventStore, PostgresCustomerEventStore, EventStatistics};
-pub use aggregate::CustomerAggregate;
+pub use aggregate::{
+    CustomerAggregate, CustomerId, MergeField, MergeFieldMap, MergeReassignments, MergeSide, MergeStrategy,
+};
 pub use analytics_engine::{CustomerAnalyticsEngine, InMemoryAnalyticsEngine, CustomerInsights};
 pub use search::{CustomerSearchEngine, AdvancedSearchEngine, SearchOptions, SearchResults, AdvancedSearchFilters};
 pub use validation::CustomerValidator;
+pub use bulk_import::{BulkImportError, BulkImportOptions, BulkImportResult, BulkImportRow};
+pub use csv_schema::{CsvRowError, CustomerCsvImport, CustomerCsvSchema, ImportedCustomer};
 
 #[cfg(feature = "axum")]
 pub use handlers::{
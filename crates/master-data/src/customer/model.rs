@@ -5,6 +5,7 @@ use std::collections::HashMap;
 use uuid::Uuid;
 use validator::Validate;
 
+use crate::customer::territory::CustomerAssignment;
 use crate::types::*;
 
 /// Comprehensive customer entity that exceeds capabilities of SAP/Oracle/Dynamics
@@ -66,6 +67,9 @@ pub struct Customer {
     // Sales & Marketing
     pub sales_representative_id: Option<Uuid>,
     pub account_manager_id: Option<Uuid>,
+    /// Derived and overridden assignees behind the two fields above
+    #[serde(default)]
+    pub assignment: CustomerAssignment,
     pub customer_segments: Vec<CustomerSegment>,
     pub acquisition_channel: Option<AcquisitionChannel>,
     pub customer_lifetime_value: Option<Decimal>,
@@ -89,6 +93,7 @@ pub struct Customer {
 /// Customer-specific types and enums
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, sqlx::Type)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[sqlx(type_name = "customer_type", rename_all = "snake_case")]
 pub enum CustomerType {
     B2b,        // Business to Business
@@ -105,6 +110,7 @@ pub enum CustomerType {
 }
 
 #[derive(Debug, Clone, Hash, Serialize, Deserialize, PartialEq, Eq, sqlx::Type)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[sqlx(type_name = "customer_lifecycle_stage", rename_all = "snake_case")]
 pub enum CustomerLifecycleStage {
     Lead,
@@ -122,6 +128,7 @@ pub enum CustomerLifecycleStage {
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[sqlx(type_name = "credit_status", rename_all = "snake_case")]
 pub enum CreditStatus {
     Excellent,
@@ -157,6 +164,7 @@ pub enum KycStatus {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::Type)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[sqlx(type_name = "acquisition_channel", rename_all = "snake_case")]
 pub enum AcquisitionChannel {
     DirectSales,
@@ -174,6 +182,7 @@ pub enum AcquisitionChannel {
 
 /// Tax jurisdiction information
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct TaxJurisdiction {
     pub jurisdiction_code: String,
     pub jurisdiction_name: String,
@@ -277,6 +286,7 @@ pub struct CustomerBehavioralData {
 
 /// Customer creation request DTO
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct CreateCustomerRequest {
     #[validate(length(max = 50))]
     pub customer_number: Option<String>, // If not provided, will be auto-generated
@@ -372,6 +382,7 @@ pub struct UpdateCustomerRequest {
 
 /// Supporting DTOs for nested structures
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct CreateAddressRequest {
     pub address_type: AddressType,
     #[validate(length(min = 1, max = 255))]
@@ -390,7 +401,36 @@ pub struct CreateAddressRequest {
     pub is_primary: Option<bool>,
 }
 
+impl CreateAddressRequest {
+    /// The shared postal address value, for validation and formatting
+    pub fn postal_address(&self) -> erp_core::Address {
+        erp_core::Address {
+            country_code: self.country_code.clone(),
+            lines: std::iter::once(self.street_line_1.clone())
+                .chain(self.street_line_2.clone())
+                .collect(),
+            city: self.city.clone(),
+            region: self.state_province.clone(),
+            postal_code: Some(self.postal_code.clone()),
+            coordinates: self.coordinates.as_ref().map(GeoCoordinates::point),
+        }
+    }
+
+    /// Clean up whitespace and casing the same way [`erp_core::Address::normalized`] does
+    pub fn normalize(&mut self) {
+        let mut normalized = self.postal_address().normalized();
+        let mut lines = normalized.lines.drain(..);
+        self.street_line_1 = lines.next().unwrap_or_default();
+        self.street_line_2 = lines.next();
+        self.city = normalized.city;
+        self.state_province = normalized.region;
+        self.postal_code = normalized.postal_code.unwrap_or_default();
+        self.country_code = normalized.country_code;
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct CreateContactRequest {
     pub contact_type: ContactType,
     #[validate(length(min = 1, max = 100))]
@@ -413,12 +453,15 @@ pub struct CreateContactRequest {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct CreateFinancialInfoRequest {
     #[validate(length(min = 3, max = 3))]
     pub currency_code: String,
     pub credit_limit: Option<Decimal>,
     pub payment_terms: Option<PaymentTerms>,
     pub tax_exempt: Option<bool>,
+    #[serde(default)]
+    pub bank_accounts: Option<Vec<BankAccount>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
@@ -428,6 +471,8 @@ pub struct UpdateFinancialInfoRequest {
     pub credit_limit: Option<Option<Decimal>>,
     pub payment_terms: Option<PaymentTerms>,
     pub tax_exempt: Option<bool>,
+    #[serde(default)]
+    pub bank_accounts: Option<Vec<BankAccount>>,
 }
 
 /// Customer search and filtering
@@ -480,6 +525,9 @@ pub struct CustomerSearchCriteria {
     // Pagination
     pub page: Option<u32>,
     pub page_size: Option<u32>,
+    /// `next_cursor` of the previous page; replaces `page`, see [`crate::customer::cursor`]
+    #[serde(default)]
+    pub cursor: Option<String>,
 
     // Sorting
     pub sort_by: Option<CustomerSortField>,
@@ -518,6 +566,9 @@ pub struct CustomerSearchResponse {
     pub page: u32,
     pub page_size: u32,
     pub total_pages: u32,
+    /// Cursor of the next page, when more customers match
+    #[serde(default)]
+    pub next_cursor: Option<String>,
 }
 
 // Default implementations
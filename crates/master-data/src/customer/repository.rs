@@ -3,12 +3,23 @@
 
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
-use sqlx::{PgPool, Row};
-use std::collections::HashMap;
+use sqlx::postgres::PgRow;
+use sqlx::{Acquire, PgConnection, PgPool, Row};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
 use uuid::Uuid;
-use serde_json;
+use serde_json::{self, Value};
 
 use crate::customer::*;
+use crate::customer::cursor::DEFAULT_CURSOR_PAGE_SIZE;
+use crate::customer::aggregate::{MergeReassignments, MergeStrategy};
+use crate::customer::bulk_import::{BulkImportError, BulkImportOptions, BulkImportPlan, BulkImportResult, BulkImportRow};
+use crate::customer::field_encryption::{
+    plaintext_matches, replace_blind_indexes, CustomerFieldCipher, MaskedFinancialIdentifiers, StoredField,
+    BANK_ACCOUNTS, TAX_NUMBERS,
+};
+use erp_core::numbering::{BlockAllocator, GapsAllowed, NumberSequence};
+use erp_core::actor::ensure_actor;
 use erp_core::TenantContext;
 use crate::types::*;
 use crate::error::{MasterDataError, Result};
@@ -17,6 +28,13 @@ use crate::error::{MasterDataError, Result};
 #[async_trait]
 pub trait CustomerRepository: Send + Sync {
     async fn create_customer(&self, request: &CreateCustomerRequest, created_by: Uuid) -> Result<Customer>;
+    /// Create many customers in batched transactions, one outcome per row; see [`crate::customer::bulk_import`]
+    async fn bulk_create_customers(
+        &self,
+        requests: Vec<CreateCustomerRequest>,
+        options: &BulkImportOptions,
+        created_by: Uuid,
+    ) -> Result<BulkImportResult>;
     async fn get_customer_by_id(&self, id: Uuid) -> Result<Option<Customer>>;
     async fn get_customer_by_number(&self, customer_number: &str) -> Result<Option<Customer>>;
     async fn update_customer(&self, id: Uuid, update: &UpdateCustomerRequest, modified_by: Uuid) -> Result<Customer>;
@@ -27,18 +45,122 @@ pub trait CustomerRepository: Send + Sync {
     async fn get_customer_addresses(&self, customer_id: Uuid) -> Result<Vec<Address>>;
     async fn get_customer_contacts(&self, customer_id: Uuid) -> Result<Vec<ContactInfo>>;
     async fn search_customers(&self, criteria: &CustomerSearchCriteria) -> Result<Vec<Customer>>;
+    /// A page of [`search_customers`](Self::search_customers) and the cursor of the next; with
+    /// `criteria.cursor` set it starts after that cursor, see [`crate::customer::cursor`]
+    async fn search_customers_page(&self, criteria: &CustomerSearchCriteria) -> Result<CustomerPage>;
     async fn is_customer_number_available(&self, customer_number: &str) -> Result<bool>;
+    /// Exact match on the normalized tax number, without decrypting
+    async fn find_customers_by_tax_number(&self, tax_number: &str) -> Result<Vec<Customer>>;
+    /// Exact match on the normalized IBAN, without decrypting
+    async fn find_customers_by_iban(&self, iban: &str) -> Result<Vec<Customer>>;
+    /// Tax numbers and bank accounts as masked responses show them, without decrypting
+    async fn get_masked_financial_identifiers(&self, customer_id: Uuid) -> Result<Option<MaskedFinancialIdentifiers>>;
 }
 
 /// PostgreSQL implementation of customer repository
+///
+/// With a [`CustomerFieldCipher`] tax numbers and bank accounts are written
+/// encrypted, see [`crate::customer::field_encryption`]. Without one they are
+/// written in plain, and reading a sealed value fails.
+///
+/// With a [`BlockAllocator`] generated customer numbers come from the
+/// `customer_numbers.<prefix>` sequences, which may have gaps; without one
+/// the highest existing number of the prefix is incremented.
 pub struct PostgresCustomerRepository {
     pool: PgPool,
     tenant_context: TenantContext,
+    cipher: Option<Arc<CustomerFieldCipher>>,
+    numbers: Option<Arc<BlockAllocator>>,
 }
 
+/// Most allocated numbers skipped because a customer created before the sequence holds them
+const MAX_TAKEN_NUMBERS_SKIPPED: usize = 1000;
+
 impl PostgresCustomerRepository {
     pub fn new(pool: PgPool, tenant_context: TenantContext) -> Self {
-        Self { pool, tenant_context }
+        Self { pool, tenant_context, cipher: None, numbers: None }
+    }
+
+    /// Encrypt tax numbers and bank accounts at rest
+    pub fn with_field_cipher(mut self, cipher: Arc<CustomerFieldCipher>) -> Self {
+        self.cipher = Some(cipher);
+        self
+    }
+
+    /// Draw generated customer numbers from block-allocated sequences
+    pub fn with_number_allocator(mut self, numbers: Arc<BlockAllocator>) -> Self {
+        self.numbers = Some(numbers);
+        self
+    }
+
+    /// Plain value of a sensitive column, decrypted if sealed
+    async fn read_sensitive(&self, row: &PgRow, field: &str) -> Result<Value> {
+        let stored = StoredField::from_json(field, row.try_get::<Option<Value>, _>(field).ok().flatten())?;
+        match (&self.cipher, &stored) {
+            (_, StoredField::Plaintext(value)) => Ok(value.clone()),
+            (Some(cipher), _) => cipher.open(self.tenant_context.tenant_id.0, field, &stored).await,
+            (None, StoredField::Encrypted { sealed, .. }) => Err(MasterDataError::FieldDecryptionFailed {
+                field: field.to_string(),
+                key_id: sealed.key_id.clone(),
+                reason: "customer field encryption is not configured".to_string(),
+            }),
+        }
+    }
+
+    /// Column value and blind indexes to write for a plain value
+    async fn seal_for_write(&self, field: &str, value: &Value) -> Result<(Value, Vec<String>)> {
+        let tenant_id = self.tenant_context.tenant_id.0;
+        match &self.cipher {
+            Some(cipher) => Ok((
+                cipher.seal(tenant_id, field, value).await?.to_json(),
+                cipher.blind_indexes(tenant_id, field, value),
+            )),
+            None => Ok((value.clone(), Vec::new())),
+        }
+    }
+
+    /// Customers whose field contains the identifier
+    async fn find_by_identifier(&self, field: &str, identifier: &str) -> Result<Vec<Customer>> {
+        let tenant_id = self.tenant_context.tenant_id.0;
+        let mut ids = BTreeSet::new();
+
+        if let Some(cipher) = &self.cipher {
+            let rows = sqlx::query(
+                "SELECT customer_id FROM customer_blind_indexes WHERE tenant_id = $1 AND field = $2 AND blind_index = $3",
+            )
+            .bind(tenant_id)
+            .bind(field)
+            .bind(cipher.lookup_index(tenant_id, field, identifier))
+            .fetch_all(&self.pool)
+            .await?;
+            for row in rows {
+                ids.insert(row.try_get::<Uuid, _>("customer_id")?);
+            }
+        }
+
+        // Rows not sealed by the backfill yet have no blind indexes.
+        // Only the fixed column names TAX_NUMBERS and BANK_ACCOUNTS reach this query.
+        let sql = format!(
+            "SELECT id, {0} AS value FROM customers \
+             WHERE tenant_id = $1 AND is_deleted = false AND {0} IS NOT NULL \
+             AND NOT (jsonb_typeof({0}) = 'object' AND {0} ? '$encrypted')",
+            field
+        );
+        let rows = sqlx::query(&sql).bind(tenant_id).fetch_all(&self.pool).await?;
+        for row in rows {
+            let value: Value = row.try_get("value")?;
+            if plaintext_matches(field, &value, identifier) {
+                ids.insert(row.try_get::<Uuid, _>("id")?);
+            }
+        }
+
+        let mut customers = Vec::new();
+        for id in ids {
+            if let Some(customer) = self.load_customer_from_db(id, false).await? {
+                customers.push(customer);
+            }
+        }
+        Ok(customers)
     }
 
     /// Load complete customer with related data from database
@@ -58,6 +180,10 @@ impl PostgresCustomerRepository {
 
         if let Some(row) = row {
             let customer_id: Uuid = row.try_get("id")?;
+            let tax_numbers: HashMap<String, String> =
+                serde_json::from_value(self.read_sensitive(&row, TAX_NUMBERS).await?).unwrap_or_default();
+            let bank_accounts: Vec<BankAccount> =
+                serde_json::from_value(self.read_sensitive(&row, BANK_ACCOUNTS).await?).unwrap_or_default();
             let mut customer = Customer {
                 id: customer_id,
                 customer_number: row.try_get("customer_number")?,
@@ -85,7 +211,7 @@ impl PostgresCustomerRepository {
                 primary_contact_id: row.try_get::<Option<Uuid>, _>("primary_contact_id").ok().flatten(),
                 contacts: Vec::new(),
                 tax_jurisdictions: row.try_get::<Option<serde_json::Value>, _>("tax_jurisdictions").ok().flatten().and_then(|v| serde_json::from_value(v).ok()).unwrap_or_default(),
-                tax_numbers: row.try_get::<Option<serde_json::Value>, _>("tax_numbers").ok().flatten().and_then(|v| serde_json::from_value(v).ok()).unwrap_or_default(),
+                tax_numbers: tax_numbers.clone(),
                 regulatory_classifications: row.try_get::<Option<serde_json::Value>, _>("regulatory_classifications").ok().flatten().and_then(|v| serde_json::from_value(v).ok()).unwrap_or_default(),
                 compliance_status: row.try_get::<Option<ComplianceStatus>, _>("compliance_status").ok().flatten().unwrap_or(ComplianceStatus::Unknown),
                 kyc_status: row.try_get::<Option<KycStatus>, _>("kyc_status").ok().flatten().unwrap_or(KycStatus::NotStarted),
@@ -95,12 +221,14 @@ impl PostgresCustomerRepository {
                     credit_limit: row.try_get::<Option<rust_decimal::Decimal>, _>("credit_limit").ok().flatten(),
                     payment_terms: row.try_get::<Option<serde_json::Value>, _>("payment_terms").ok().flatten().and_then(|v| serde_json::from_value(v).ok()),
                     tax_exempt: row.try_get::<bool, _>("tax_exempt").ok().unwrap_or(false),
-                    tax_numbers: row.try_get::<Option<serde_json::Value>, _>("tax_numbers").ok().flatten().and_then(|v| serde_json::from_value(v).ok()).unwrap_or_default(),
+                    tax_numbers,
+                    bank_accounts,
                 },
                 price_group_id: row.try_get::<Option<Uuid>, _>("price_group_id").ok().flatten(),
                 discount_group_id: row.try_get::<Option<Uuid>, _>("discount_group_id").ok().flatten(),
                 sales_representative_id: row.try_get::<Option<Uuid>, _>("sales_representative_id").ok().flatten(),
                 account_manager_id: row.try_get::<Option<Uuid>, _>("account_manager_id").ok().flatten(),
+                assignment: customer_assignment(&row),
                 customer_segments: row.try_get::<Option<serde_json::Value>, _>("customer_segments").ok().flatten().and_then(|v| serde_json::from_value(v).ok()).unwrap_or_default(),
                 acquisition_channel: row.try_get::<Option<AcquisitionChannel>, _>("acquisition_channel").ok().flatten(),
                 customer_lifetime_value: row.try_get::<Option<rust_decimal::Decimal>, _>("customer_lifetime_value").ok().flatten(),
@@ -186,8 +314,170 @@ impl PostgresCustomerRepository {
         }
     }
 
+    /// Insert the customer with its addresses, contacts and blind indexes
+    async fn insert_customer(
+        &self,
+        conn: &mut PgConnection,
+        request: &CreateCustomerRequest,
+        customer_number: String,
+        created_by: Uuid,
+    ) -> Result<Uuid> {
+        let customer_id = Uuid::new_v4();
+        let now = Utc::now();
+
+        // Prepare values to avoid temporary references
+        let default_currency = "USD".to_string();
+        let currency_code = request.financial_info.as_ref()
+            .map(|f| &f.currency_code)
+            .unwrap_or(&default_currency);
+        let payment_terms_json = request.financial_info.as_ref()
+            .and_then(|f| f.payment_terms.as_ref()
+                .map(|pt| serde_json::to_value(pt).unwrap_or(serde_json::Value::Null)))
+            .unwrap_or(serde_json::Value::Null);
+        let tax_exempt = request.financial_info.as_ref()
+            .and_then(|f| f.tax_exempt)
+            .unwrap_or(false);
+        let (tax_numbers, tax_number_indexes) = self
+            .seal_for_write(TAX_NUMBERS, &serde_json::to_value(&request.tax_numbers)?)
+            .await?;
+        let bank_accounts = request.financial_info.as_ref()
+            .and_then(|f| f.bank_accounts.clone())
+            .unwrap_or_default();
+        let (bank_accounts, bank_account_indexes) = self
+            .seal_for_write(BANK_ACCOUNTS, &serde_json::to_value(&bank_accounts)?)
+            .await?;
+
+        // Insert customer with proper type casting; assignees given on creation are overrides
+        sqlx::query(
+            r#"
+            INSERT INTO customers (
+                id, tenant_id, customer_number, legal_name, trade_names,
+                customer_type, industry_classification, business_size,
+                parent_customer_id, corporate_group_id, customer_hierarchy_level, consolidation_group,
+                lifecycle_stage, status, credit_status,
+                tax_jurisdictions, tax_numbers,
+                currency_code, credit_limit, payment_terms, tax_exempt,
+                sales_representative_id, account_manager_id, acquisition_channel,
+                sales_representative_override_id, account_manager_override_id,
+                external_ids, master_data_source, external_id, sync_status,
+                created_by, created_at, modified_by, modified_at, bank_accounts
+            ) VALUES (
+                $1, $2, $3, $4, $5,
+                $6::customer_type, $7::industry_classification, $8::business_size,
+                $9, $10, $11, $12,
+                $13::customer_lifecycle_stage, $14::entity_status, $15::credit_status,
+                $16, $17,
+                $18, $19, $20, $21,
+                $22, $23, $24::acquisition_channel,
+                $22, $23,
+                $25, $26::data_source, $27, $28,
+                $29, $30, $31, $32, $33
+            )
+            "#,
+        )
+        .bind(customer_id)
+        .bind(self.tenant_context.tenant_id.0)
+        .bind(customer_number)
+        .bind(&request.legal_name)
+        .bind(serde_json::to_value(&request.trade_names)?)
+        .bind(request.customer_type.clone())
+        .bind(request.industry_classification.clone().unwrap_or(IndustryClassification::Other))
+        .bind(request.business_size.clone().unwrap_or(BusinessSize::Small))
+        .bind(request.parent_customer_id)
+        .bind(request.corporate_group_id)
+        .bind(request.customer_hierarchy_level.unwrap_or(1u8) as i16)
+        .bind(request.consolidation_group.clone())
+        .bind(request.lifecycle_stage.clone().unwrap_or(CustomerLifecycleStage::Prospect))
+        .bind(request.status.clone().unwrap_or(EntityStatus::Active))
+        .bind(request.credit_status.clone().unwrap_or(CreditStatus::Good))
+        .bind(serde_json::to_value(&request.tax_jurisdictions)?)
+        .bind(&tax_numbers)
+        .bind(currency_code)
+        .bind(request.financial_info.as_ref().and_then(|f| f.credit_limit))
+        .bind(payment_terms_json)
+        .bind(tax_exempt)
+        .bind(request.sales_representative_id)
+        .bind(request.account_manager_id)
+        .bind(request.acquisition_channel.clone())
+        .bind(serde_json::to_value(&request.external_ids)?)
+        .bind(DataSource::Manual as DataSource)
+        .bind(Option::<String>::None)
+        .bind(SyncStatus::Success as SyncStatus)
+        .bind(created_by)
+        .bind(now)
+        .bind(created_by)
+        .bind(now)
+        .bind(&bank_accounts)
+        .execute(&mut *conn)
+        .await?;
+
+        let tenant_id = self.tenant_context.tenant_id.0;
+        replace_blind_indexes(&mut *conn, tenant_id, customer_id, TAX_NUMBERS, &tax_number_indexes).await?;
+        replace_blind_indexes(&mut *conn, tenant_id, customer_id, BANK_ACCOUNTS, &bank_account_indexes).await?;
+
+        // Addresses and contacts are read back in the order they were given, by creation time
+        for (position, address) in request.addresses.iter().flatten().enumerate() {
+            sqlx::query(
+                r#"
+                INSERT INTO addresses (
+                    entity_type, entity_id, address_type, street_line_1, street_line_2, city,
+                    state_province, postal_code, country_code, is_primary,
+                    created_at, updated_at, created_by, updated_by
+                ) VALUES ('customer', $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $10, $11, $11)
+                "#,
+            )
+            .bind(customer_id)
+            .bind(address.address_type)
+            .bind(&address.street_line_1)
+            .bind(&address.street_line_2)
+            .bind(&address.city)
+            .bind(&address.state_province)
+            .bind(&address.postal_code)
+            .bind(&address.country_code)
+            .bind(address.is_primary.unwrap_or(false))
+            .bind(now + chrono::Duration::microseconds(position as i64))
+            .bind(created_by)
+            .execute(&mut *conn)
+            .await?;
+        }
+        for (position, contact) in request.contacts.iter().flatten().enumerate() {
+            sqlx::query(
+                r#"
+                INSERT INTO contact_info (
+                    entity_type, entity_id, contact_type, first_name, last_name, title, department,
+                    email, phone, mobile, is_primary,
+                    created_at, updated_at, created_by, updated_by
+                ) VALUES ('customer', $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $11, $12, $12)
+                "#,
+            )
+            .bind(customer_id)
+            .bind(contact.contact_type)
+            .bind(&contact.first_name)
+            .bind(&contact.last_name)
+            .bind(&contact.title)
+            .bind(&contact.department)
+            .bind(&contact.email)
+            .bind(&contact.phone)
+            .bind(&contact.mobile)
+            .bind(contact.is_primary.unwrap_or(false))
+            .bind(now + chrono::Duration::microseconds(position as i64))
+            .bind(created_by)
+            .execute(&mut *conn)
+            .await?;
+        }
+
+        Ok(customer_id)
+    }
+
     /// Generate a unique customer number based on customer type
     async fn generate_customer_number(&self, customer_type: &CustomerType) -> Result<String> {
+        let mut conn = self.pool.acquire().await?;
+        self.generate_customer_number_on(&mut conn, customer_type).await
+    }
+
+    /// [`Self::generate_customer_number`] on `conn`, so numbers of customers
+    /// inserted earlier in its transaction are not handed out again
+    async fn generate_customer_number_on(&self, conn: &mut PgConnection, customer_type: &CustomerType) -> Result<String> {
         let prefix = match customer_type {
             CustomerType::B2b => "B",
             CustomerType::B2c => "C",
@@ -202,6 +492,23 @@ impl PostgresCustomerRepository {
             CustomerType::Prospect => "P",
         };
 
+        if let Some(numbers) = &self.numbers {
+            let sequence = NumberSequence::<GapsAllowed>::new(format!("customer_numbers.{}", prefix));
+            for _ in 0..MAX_TAKEN_NUMBERS_SKIPPED {
+                let number = numbers.next(self.tenant_context.tenant_id.0, &sequence).await?;
+                let customer_number = format!("{}{:06}", prefix, number);
+                // Customers created before the sequence existed may hold the number already
+                if Self::customer_number_available_on(conn, self.tenant_context.tenant_id.0, &customer_number).await? {
+                    return Ok(customer_number);
+                }
+            }
+            return Err(erp_core::Error::conflict(format!(
+                "No free customer number found in sequence {}",
+                sequence.name()
+            ))
+            .into());
+        }
+
         // Get the next sequence number for this type
         let row = sqlx::query(
             r#"
@@ -212,7 +519,7 @@ impl PostgresCustomerRepository {
         )
         .bind(self.tenant_context.tenant_id.0)
         .bind(format!("{}%", prefix))
-        .fetch_one(&self.pool)
+        .fetch_one(&mut *conn)
         .await?;
 
         Ok(format!("{}{:06}", prefix, row.try_get::<Option<i32>, _>("next_number")?.unwrap_or(1)))
@@ -220,17 +527,61 @@ impl PostgresCustomerRepository {
 
     /// Check if customer number is available
     async fn is_customer_number_available(&self, customer_number: &str) -> Result<bool> {
+        let mut conn = self.pool.acquire().await?;
+        Self::customer_number_available_on(&mut conn, self.tenant_context.tenant_id.0, customer_number).await
+    }
+
+    async fn customer_number_available_on(conn: &mut PgConnection, tenant_id: Uuid, customer_number: &str) -> Result<bool> {
         let count = sqlx::query(
             "SELECT COUNT(*) as count FROM customers WHERE tenant_id = $1 AND customer_number = $2 AND is_deleted = false",
         )
-        .bind(self.tenant_context.tenant_id.0)
+        .bind(tenant_id)
         .bind(customer_number)
-        .fetch_one(&self.pool)
+        .fetch_one(&mut *conn)
         .await?;
 
         Ok(count.try_get::<Option<i64>, _>("count")?.unwrap_or(0) == 0)
     }
 
+    /// Insert one row of a bulk import on its own savepoint of `tx`, so a
+    /// failing row leaves the rest of the batch intact
+    async fn insert_import_row(
+        &self,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        request: &CreateCustomerRequest,
+        created_by: Uuid,
+    ) -> std::result::Result<(Uuid, String), BulkImportError> {
+        let customer_number = request.customer_number.as_deref();
+        let fail = |e: MasterDataError| BulkImportError::from_error(e, customer_number);
+
+        let mut row = tx.begin().await.map_err(|e| fail(e.into()))?;
+        let inserted = async {
+            let customer_number = match &request.customer_number {
+                Some(number) => {
+                    if !Self::customer_number_available_on(&mut row, self.tenant_context.tenant_id.0, number).await? {
+                        return Err(MasterDataError::DuplicateCustomerNumber { number: number.clone() });
+                    }
+                    number.clone()
+                }
+                None => self.generate_customer_number_on(&mut row, &request.customer_type).await?,
+            };
+            let customer_id = self.insert_customer(&mut row, request, customer_number.clone(), created_by).await?;
+            Ok((customer_id, customer_number))
+        }
+        .await;
+
+        match inserted {
+            Ok(created) => {
+                row.commit().await.map_err(|e| fail(e.into()))?;
+                Ok(created)
+            }
+            Err(e) => {
+                row.rollback().await.map_err(|e| fail(e.into()))?;
+                Err(fail(e))
+            }
+        }
+    }
+
     /// Load performance metrics for a customer
     async fn get_performance_metrics(&self, customer_id: Uuid) -> Result<Option<CustomerPerformanceMetrics>> {
         let row = sqlx::query(
@@ -309,6 +660,7 @@ impl PostgresCustomerRepository {
 #[async_trait]
 impl CustomerRepository for PostgresCustomerRepository {
     async fn create_customer(&self, request: &CreateCustomerRequest, created_by: Uuid) -> Result<Customer> {
+        ensure_actor(created_by)?;
         let mut tx = self.pool.begin().await?;
 
         // Generate customer number if not provided
@@ -324,82 +676,7 @@ impl CustomerRepository for PostgresCustomerRepository {
             self.generate_customer_number(&request.customer_type).await?
         };
 
-        let customer_id = Uuid::new_v4();
-        let now = Utc::now();
-
-        // Prepare values to avoid temporary references
-        let default_currency = "USD".to_string();
-        let currency_code = request.financial_info.as_ref()
-            .map(|f| &f.currency_code)
-            .unwrap_or(&default_currency);
-        let payment_terms_json = request.financial_info.as_ref()
-            .and_then(|f| f.payment_terms.as_ref()
-                .map(|pt| serde_json::to_value(pt).unwrap_or(serde_json::Value::Null)))
-            .unwrap_or(serde_json::Value::Null);
-        let tax_exempt = request.financial_info.as_ref()
-            .and_then(|f| f.tax_exempt)
-            .unwrap_or(false);
-
-        // Insert customer with proper type casting
-        sqlx::query(
-            r#"
-            INSERT INTO customers (
-                id, tenant_id, customer_number, legal_name, trade_names,
-                customer_type, industry_classification, business_size,
-                parent_customer_id, corporate_group_id, customer_hierarchy_level, consolidation_group,
-                lifecycle_stage, status, credit_status,
-                tax_jurisdictions, tax_numbers,
-                currency_code, credit_limit, payment_terms, tax_exempt,
-                sales_representative_id, account_manager_id, acquisition_channel,
-                external_ids, master_data_source, external_id, sync_status,
-                created_by, created_at, modified_by, modified_at
-            ) VALUES (
-                $1, $2, $3, $4, $5,
-                $6::customer_type, $7::industry_classification, $8::business_size,
-                $9, $10, $11, $12,
-                $13::customer_lifecycle_stage, $14::entity_status, $15::credit_status,
-                $16, $17,
-                $18, $19, $20, $21,
-                $22, $23, $24::acquisition_channel,
-                $25, $26::data_source, $27, $28,
-                $29, $30, $31, $32
-            )
-            "#,
-        )
-        .bind(customer_id)
-        .bind(self.tenant_context.tenant_id.0)
-        .bind(customer_number)
-        .bind(&request.legal_name)
-        .bind(serde_json::to_value(&request.trade_names)?)
-        .bind(request.customer_type.clone())
-        .bind(request.industry_classification.clone().unwrap_or(IndustryClassification::Other))
-        .bind(request.business_size.clone().unwrap_or(BusinessSize::Small))
-        .bind(request.parent_customer_id)
-        .bind(request.corporate_group_id)
-        .bind(request.customer_hierarchy_level.unwrap_or(1u8) as i16)
-        .bind(request.consolidation_group.clone())
-        .bind(request.lifecycle_stage.clone().unwrap_or(CustomerLifecycleStage::Prospect))
-        .bind(EntityStatus::Active as EntityStatus)
-        .bind(CreditStatus::Good as CreditStatus)
-        .bind(serde_json::to_value(&request.tax_jurisdictions)?)
-        .bind(serde_json::to_value(&request.tax_numbers)?)
-        .bind(currency_code)
-        .bind(request.financial_info.as_ref().and_then(|f| f.credit_limit))
-        .bind(payment_terms_json)
-        .bind(tax_exempt)
-        .bind(request.sales_representative_id)
-        .bind(request.account_manager_id)
-        .bind(request.acquisition_channel.clone())
-        .bind(serde_json::to_value(&request.external_ids)?)
-        .bind(DataSource::Manual as DataSource)
-        .bind(Option::<String>::None)
-        .bind(SyncStatus::Success as SyncStatus)
-        .bind(created_by)
-        .bind(now)
-        .bind(created_by)
-        .bind(now)
-        .execute(&mut *tx)
-        .await?;
+        let customer_id = self.insert_customer(&mut tx, request, customer_number, created_by).await?;
 
         tx.commit().await?;
 
@@ -420,6 +697,57 @@ impl CustomerRepository for PostgresCustomerRepository {
         Ok(customer)
     }
 
+    async fn bulk_create_customers(
+        &self,
+        requests: Vec<CreateCustomerRequest>,
+        options: &BulkImportOptions,
+        created_by: Uuid,
+    ) -> Result<BulkImportResult> {
+        ensure_actor(created_by)?;
+        let plan = BulkImportPlan::new(requests, options);
+        let mut rows = plan.rejected;
+        if options.atomic && !rows.is_empty() {
+            rows.extend(plan.batches.into_iter().flatten().map(|(index, _)| BulkImportRow::Failed {
+                index,
+                error: BulkImportError::Aborted,
+            }));
+            return Ok(BulkImportResult::aborted(rows));
+        }
+
+        for batch in plan.batches {
+            let mut tx = self.pool.begin().await?;
+            let mut batch_rows = Vec::with_capacity(batch.len());
+            for (index, request) in batch {
+                batch_rows.push(match self.insert_import_row(&mut tx, &request, created_by).await {
+                    Ok((customer_id, customer_number)) => BulkImportRow::Created { index, customer_id, customer_number },
+                    Err(error) => BulkImportRow::Failed { index, error },
+                });
+            }
+
+            if options.atomic && batch_rows.iter().any(|row| !row.is_created()) {
+                tx.rollback().await?;
+                rows.extend(batch_rows);
+                return Ok(BulkImportResult::aborted(rows));
+            }
+            match tx.commit().await {
+                Ok(()) => rows.extend(batch_rows),
+                Err(e) => {
+                    tracing::error!("Failed to commit a batch of {} imported customers: {}", batch_rows.len(), e);
+                    let message = e.to_string();
+                    rows.extend(batch_rows.into_iter().map(|row| match row {
+                        BulkImportRow::Created { index, .. } => BulkImportRow::Failed {
+                            index,
+                            error: BulkImportError::Database { message: message.clone() },
+                        },
+                        failed => failed,
+                    }));
+                }
+            }
+        }
+
+        Ok(BulkImportResult::new(rows))
+    }
+
     async fn get_customer_by_id(&self, id: Uuid) -> Result<Option<Customer>> {
         self.load_customer_from_db(id, true).await
     }
@@ -442,6 +770,7 @@ impl CustomerRepository for PostgresCustomerRepository {
     }
 
     async fn update_customer(&self, id: Uuid, update: &UpdateCustomerRequest, modified_by: Uuid) -> Result<Customer> {
+        ensure_actor(modified_by)?;
         let mut tx = self.pool.begin().await?;
         let now = Utc::now();
 
@@ -474,6 +803,25 @@ impl CustomerRepository for PostgresCustomerRepository {
         .execute(&mut *tx)
         .await?;
 
+        let mut sensitive = Vec::new();
+        if let Some(tax_numbers) = &update.tax_numbers {
+            sensitive.push((TAX_NUMBERS, serde_json::to_value(tax_numbers)?));
+        }
+        if let Some(bank_accounts) = update.financial_info.as_ref().and_then(|f| f.bank_accounts.as_ref()) {
+            sensitive.push((BANK_ACCOUNTS, serde_json::to_value(bank_accounts)?));
+        }
+        for (field, value) in sensitive {
+            let (stored, indexes) = self.seal_for_write(field, &value).await?;
+            // Only the fixed column names TAX_NUMBERS and BANK_ACCOUNTS reach this query
+            sqlx::query(&format!("UPDATE customers SET {} = $1 WHERE id = $2 AND tenant_id = $3", field))
+                .bind(&stored)
+                .bind(id)
+                .bind(self.tenant_context.tenant_id.0)
+                .execute(&mut *tx)
+                .await?;
+            replace_blind_indexes(&mut tx, self.tenant_context.tenant_id.0, id, field, &indexes).await?;
+        }
+
         tx.commit().await?;
 
         // Return updated customer
@@ -482,6 +830,7 @@ impl CustomerRepository for PostgresCustomerRepository {
     }
 
     async fn delete_customer(&self, id: Uuid, deleted_by: Uuid) -> Result<()> {
+        ensure_actor(deleted_by)?;
         let now = Utc::now();
 
         sqlx::query(
@@ -537,6 +886,7 @@ impl CustomerRepository for PostgresCustomerRepository {
             page,
             page_size,
             total_pages,
+            next_cursor: None,
         })
     }
 
@@ -606,16 +956,16 @@ impl CustomerRepository for PostgresCustomerRepository {
     }
 
     async fn get_customer_addresses(&self, customer_id: Uuid) -> Result<Vec<Address>> {
-        // Load all addresses for a customer from the customer_addresses table
+        // Active addresses of the customer in the order they were created
         let rows = sqlx::query(
             r#"
-            SELECT ca.address_id, ca.address_type, ca.is_primary,
-                   a.street_address, a.city, a.state_province, a.postal_code,
-                   a.country_code, a.address_type as addr_type, a.latitude, a.longitude
-            FROM customer_addresses ca
-            INNER JOIN addresses a ON ca.address_id = a.id
-            WHERE ca.customer_id = $1 AND ca.tenant_id = $2
-            ORDER BY ca.is_primary DESC, ca.address_type
+            SELECT a.id, a.address_type, a.street_line_1, a.street_line_2, a.city, a.state_province,
+                   a.postal_code, a.country_code, a.is_primary, a.is_active,
+                   a.created_by, a.created_at, a.updated_by, a.updated_at
+            FROM addresses a
+            JOIN customers c ON c.id = a.entity_id AND c.tenant_id = $2
+            WHERE a.entity_type = 'customer' AND a.entity_id = $1 AND a.is_active
+            ORDER BY a.created_at, a.id
             "#,
         )
         .bind(customer_id)
@@ -626,24 +976,25 @@ impl CustomerRepository for PostgresCustomerRepository {
         let mut addresses = Vec::new();
         for row in rows {
             let address = Address {
-                id: row.try_get("address_id")?,
+                id: row.try_get("id")?,
                 entity_type: "customer".to_string(),
                 entity_id: customer_id,
-                address_type: row.try_get::<AddressType, _>("addr_type").unwrap_or(AddressType::Business),
-                street_line_1: row.try_get("street_address")?,
-                street_line_2: None,
+                address_type: row.try_get::<AddressType, _>("address_type").unwrap_or(AddressType::Business),
+                street_line_1: row.try_get("street_line_1")?,
+                street_line_2: row.try_get::<Option<String>, _>("street_line_2")?,
                 city: row.try_get("city")?,
                 state_province: row.try_get::<Option<String>, _>("state_province")?,
                 postal_code: row.try_get("postal_code")?,
-                country_code: row.try_get("country_code")?,
-                coordinates: None, // Would need to construct from lat/lng if available
-                is_primary: row.try_get::<bool, _>("is_primary").unwrap_or(false),
-                is_active: true,
+                // CHAR(3) pads two-letter codes
+                country_code: row.try_get::<String, _>("country_code")?.trim_end().to_string(),
+                coordinates: None,
+                is_primary: row.try_get("is_primary")?,
+                is_active: row.try_get("is_active")?,
                 audit: AuditFields {
-                    created_by: uuid::Uuid::new_v4(),
-                    created_at: chrono::Utc::now(),
-                    modified_by: uuid::Uuid::new_v4(),
-                    modified_at: chrono::Utc::now(),
+                    created_by: row.try_get("created_by")?,
+                    created_at: row.try_get("created_at")?,
+                    modified_by: row.try_get("updated_by")?,
+                    modified_at: row.try_get("updated_at")?,
                     version: 1,
                     is_deleted: false,
                     deleted_at: None,
@@ -656,16 +1007,16 @@ impl CustomerRepository for PostgresCustomerRepository {
     }
 
     async fn get_customer_contacts(&self, customer_id: Uuid) -> Result<Vec<ContactInfo>> {
-        // Load all contacts for a customer from the customer_contacts table
+        // Active contacts of the customer in the order they were created
         let rows = sqlx::query(
             r#"
-            SELECT cc.contact_id, cc.contact_type, cc.is_primary,
-                   c.first_name, c.last_name, c.email, c.phone, c.job_title,
-                   c.department, c.is_decision_maker, c.preferred_contact_method
-            FROM customer_contacts cc
-            INNER JOIN contacts c ON cc.contact_id = c.id
-            WHERE cc.customer_id = $1 AND cc.tenant_id = $2
-            ORDER BY cc.is_primary DESC, c.last_name, c.first_name
+            SELECT ci.id, ci.contact_type, ci.first_name, ci.last_name, ci.title, ci.department,
+                   ci.email, ci.phone, ci.mobile, ci.website, ci.timezone, ci.notes, ci.tags,
+                   ci.is_primary, ci.is_active, ci.created_by, ci.created_at, ci.updated_by, ci.updated_at
+            FROM contact_info ci
+            JOIN customers c ON c.id = ci.entity_id AND c.tenant_id = $2
+            WHERE ci.entity_type = 'customer' AND ci.entity_id = $1 AND ci.is_active
+            ORDER BY ci.created_at, ci.id
             "#,
         )
         .bind(customer_id)
@@ -676,32 +1027,32 @@ impl CustomerRepository for PostgresCustomerRepository {
         let mut contacts = Vec::new();
         for row in rows {
             let contact = ContactInfo {
-                id: row.try_get("contact_id")?,
+                id: row.try_get("id")?,
                 entity_type: "customer".to_string(),
                 entity_id: customer_id,
                 contact_type: row.try_get::<ContactType, _>("contact_type").unwrap_or(ContactType::Primary),
-                first_name: row.try_get("first_name")?,
-                last_name: row.try_get("last_name")?,
-                title: row.try_get::<Option<String>, _>("job_title")?,
+                first_name: row.try_get::<Option<String>, _>("first_name")?.unwrap_or_default(),
+                last_name: row.try_get::<Option<String>, _>("last_name")?.unwrap_or_default(),
+                title: row.try_get::<Option<String>, _>("title")?,
                 department: row.try_get::<Option<String>, _>("department")?,
                 email: row.try_get::<Option<String>, _>("email")?,
                 phone: row.try_get::<Option<String>, _>("phone")?,
-                mobile: None,
+                mobile: row.try_get::<Option<String>, _>("mobile")?,
                 fax: None,
-                website: None,
+                website: row.try_get::<Option<String>, _>("website")?,
                 social_media_accounts: Some(HashMap::new()),
                 preferred_language: None,
                 communication_preferences: None,
-                timezone: None,
-                notes: None,
-                tags: vec![],
-                is_primary: row.try_get::<bool, _>("is_primary").unwrap_or(false),
-                is_active: true,
+                timezone: row.try_get::<Option<String>, _>("timezone")?,
+                notes: row.try_get::<Option<String>, _>("notes")?,
+                tags: row.try_get::<Option<Vec<String>>, _>("tags")?.unwrap_or_default(),
+                is_primary: row.try_get("is_primary")?,
+                is_active: row.try_get("is_active")?,
                 audit: AuditFields {
-                    created_by: uuid::Uuid::new_v4(),
-                    created_at: chrono::Utc::now(),
-                    modified_by: uuid::Uuid::new_v4(),
-                    modified_at: chrono::Utc::now(),
+                    created_by: row.try_get("created_by")?,
+                    created_at: row.try_get("created_at")?,
+                    modified_by: row.try_get("updated_by")?,
+                    modified_at: row.try_get("updated_at")?,
                     version: 1,
                     is_deleted: false,
                     deleted_at: None,
@@ -714,8 +1065,16 @@ impl CustomerRepository for PostgresCustomerRepository {
     }
 
     async fn search_customers(&self, criteria: &CustomerSearchCriteria) -> Result<Vec<Customer>> {
+        Ok(self.search_customers_page(criteria).await?.customers)
+    }
+
+    async fn search_customers_page(&self, criteria: &CustomerSearchCriteria) -> Result<CustomerPage> {
+        let order = CustomerOrder::of(criteria);
+        let after = CustomerCursor::of(criteria, order)?;
+        let sort_column = order.key.column();
+
         let mut query_builder = sqlx::QueryBuilder::new(
-            "SELECT id FROM customers WHERE tenant_id = "
+            format!("SELECT id, {}::text AS sort_key FROM customers WHERE tenant_id = ", sort_column)
         );
         query_builder.push_bind(self.tenant_context.tenant_id.0);
         query_builder.push(" AND is_deleted = false");
@@ -759,19 +1118,44 @@ impl CustomerRepository for PostgresCustomerRepository {
             }
         }
 
-        query_builder.push(" ORDER BY legal_name");
+        // Keyset pagination: continue after the cursor's row instead of skipping rows
+        if let Some(after) = &after {
+            query_builder.push(format!(" AND ({}, id) {} (", sort_column, order.after()));
+            query_builder.push_bind(&after.key);
+            query_builder.push(format!("::{}, ", order.key.sql_type()));
+            query_builder.push_bind(after.id);
+            query_builder.push(")");
+        }
 
-        // Add pagination if specified
-        if let (Some(page), Some(page_size)) = (criteria.page, criteria.page_size) {
-            let offset = (page.saturating_sub(1)) * page_size;
+        query_builder.push(format!(" ORDER BY {0} {1}, id {1}", sort_column, order.direction()));
+
+        // Add pagination if specified; one row more tells whether another page follows
+        let page_size = match (&after, criteria.page_size) {
+            (Some(_), page_size) => Some(page_size.unwrap_or(DEFAULT_CURSOR_PAGE_SIZE)),
+            (None, page_size) => page_size.filter(|_| criteria.page.is_some()),
+        };
+        if let Some(page_size) = page_size {
             query_builder.push(" LIMIT ");
-            query_builder.push_bind(page_size as i64);
-            query_builder.push(" OFFSET ");
-            query_builder.push_bind(offset as i64);
+            query_builder.push_bind(page_size as i64 + 1);
+            if after.is_none() {
+                let offset = (criteria.page.unwrap_or(1).saturating_sub(1)) as i64 * page_size as i64;
+                query_builder.push(" OFFSET ");
+                query_builder.push_bind(offset);
+            }
         }
 
         let query = query_builder.build();
-        let rows = query.fetch_all(&self.pool).await?;
+        let mut rows = query.fetch_all(&self.pool).await?;
+
+        let mut next_cursor = None;
+        if let Some(page_size) = page_size {
+            if rows.len() > page_size as usize {
+                rows.truncate(page_size as usize);
+                if let Some(last) = rows.last() {
+                    next_cursor = Some(CustomerCursor::new(order, last.try_get::<String, _>("sort_key")?, last.try_get("id")?).encode());
+                }
+            }
+        }
 
         let mut customers = Vec::new();
         for row in rows {
@@ -780,7 +1164,7 @@ impl CustomerRepository for PostgresCustomerRepository {
                 customers.push(customer);
             }
         }
-        Ok(customers)
+        Ok(CustomerPage { customers, next_cursor })
     }
 
     async fn is_customer_number_available(&self, customer_number: &str) -> Result<bool> {
@@ -794,4 +1178,750 @@ impl CustomerRepository for PostgresCustomerRepository {
 
         Ok(row.try_get::<Option<i64>, _>("count")?.unwrap_or(0) == 0)
     }
-}
\ No newline at end of file
+
+    async fn find_customers_by_tax_number(&self, tax_number: &str) -> Result<Vec<Customer>> {
+        self.find_by_identifier(TAX_NUMBERS, tax_number).await
+    }
+
+    async fn find_customers_by_iban(&self, iban: &str) -> Result<Vec<Customer>> {
+        self.find_by_identifier(BANK_ACCOUNTS, iban).await
+    }
+
+    async fn get_masked_financial_identifiers(&self, customer_id: Uuid) -> Result<Option<MaskedFinancialIdentifiers>> {
+        let row = sqlx::query(
+            "SELECT tax_numbers, bank_accounts FROM customers WHERE id = $1 AND tenant_id = $2 AND is_deleted = false",
+        )
+        .bind(customer_id)
+        .bind(self.tenant_context.tenant_id.0)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        let masked = |field: &str| -> Result<Value> {
+            Ok(StoredField::from_json(field, row.try_get::<Option<Value>, _>(field)?)?.masked(field))
+        };
+        Ok(Some(MaskedFinancialIdentifiers {
+            tax_numbers: serde_json::from_value(masked(TAX_NUMBERS)?).unwrap_or_default(),
+            bank_accounts: serde_json::from_value(masked(BANK_ACCOUNTS)?).unwrap_or_default(),
+        }))
+    }
+}
+
+/// Merges of duplicate customers, see [`CustomerAggregate::merge`]
+#[async_trait]
+pub trait CustomerMergeRepository: Send + Sync {
+    /// Merge `source` into `target` in one transaction and return the target;
+    /// a source already merged into the target returns it unchanged
+    async fn merge_customers(
+        &self,
+        source: Uuid,
+        target: Uuid,
+        strategy: &MergeStrategy,
+        merged_by: Uuid,
+    ) -> Result<Customer>;
+}
+
+#[async_trait]
+impl CustomerMergeRepository for PostgresCustomerRepository {
+    async fn merge_customers(
+        &self,
+        source: Uuid,
+        target: Uuid,
+        strategy: &MergeStrategy,
+        merged_by: Uuid,
+    ) -> Result<Customer> {
+        ensure_actor(merged_by)?;
+        let tenant_id = self.tenant_context.tenant_id.0;
+        let mut tx = self.pool.begin().await?;
+
+        // Locked in id order, so concurrent merges of the same customers queue up
+        let locked: Vec<Uuid> = sqlx::query_scalar(
+            "SELECT id FROM customers WHERE id = ANY($1) AND tenant_id = $2 ORDER BY id FOR UPDATE",
+        )
+        .bind(vec![source, target])
+        .bind(tenant_id)
+        .fetch_all(&mut *tx)
+        .await?;
+        if let Some(missing) = [source, target].into_iter().find(|id| !locked.contains(id)) {
+            return Err(MasterDataError::CustomerNotFound { id: missing.to_string() });
+        }
+
+        // The audit trail entry is written last, so its presence means the merge is complete
+        let merged_into: Option<Uuid> = sqlx::query_scalar(
+            "SELECT target_customer_id FROM customer_merges WHERE tenant_id = $1 AND source_customer_id = $2",
+        )
+        .bind(tenant_id)
+        .bind(source)
+        .fetch_optional(&mut *tx)
+        .await?;
+        match merged_into {
+            Some(merged_into) if merged_into == target => {
+                tx.rollback().await?;
+                return self
+                    .get_customer_by_id(target)
+                    .await?
+                    .ok_or(MasterDataError::CustomerNotFound { id: target.to_string() });
+            }
+            Some(merged_into) => {
+                return Err(MasterDataError::ValidationError {
+                    field: "source".to_string(),
+                    message: format!("Customer {} was already merged into {}", source, merged_into),
+                });
+            }
+            None => {}
+        }
+
+        // Read outside the transaction; the locks keep both rows as read
+        let source_customer = self
+            .load_customer_from_db(source, false)
+            .await?
+            .ok_or(MasterDataError::CustomerNotFound { id: source.to_string() })?;
+        let target_customer = self
+            .load_customer_from_db(target, false)
+            .await?
+            .ok_or(MasterDataError::CustomerNotFound { id: target.to_string() })?;
+
+        let mut reassigned = MergeReassignments::default();
+        for table in ["sales_transactions", "return_orders"] {
+            // Only the fixed table names above reach this query
+            reassigned.orders += sqlx::query(&format!(
+                "UPDATE {} SET customer_id = $1, updated_at = NOW() WHERE customer_id = $2 AND tenant_id = $3",
+                table
+            ))
+            .bind(target)
+            .bind(source)
+            .bind(tenant_id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        }
+
+        // The target's primary contact stays its primary one
+        reassigned.contacts = sqlx::query(
+            r#"
+            UPDATE contact_info
+            SET entity_id = $1,
+                is_primary = is_primary AND NOT EXISTS (
+                    SELECT 1 FROM contact_info p
+                    WHERE p.entity_type = 'customer' AND p.entity_id = $1 AND p.is_primary
+                ),
+                updated_by = $3
+            WHERE entity_type = 'customer' AND entity_id = $2
+            "#,
+        )
+        .bind(target)
+        .bind(source)
+        .bind(merged_by)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        let tax_jurisdictions = CustomerAggregate::merged_tax_jurisdictions(&source_customer, &target_customer);
+        reassigned.tax_jurisdictions = (tax_jurisdictions.len() - target_customer.tax_jurisdictions.len()) as u64;
+
+        let merged = CustomerAggregate::merge_event(&source_customer, &target_customer, strategy, reassigned.clone(), merged_by)?;
+        let CustomerEvent::Merged {
+            legal_name,
+            customer_type,
+            currency_code,
+            credit_limit,
+            payment_terms,
+            tax_exempt,
+            fields_from_source,
+            merged_at,
+            ..
+        } = &merged
+        else {
+            unreachable!("merge_event returns a merge");
+        };
+
+        sqlx::query(
+            r#"
+            UPDATE customers
+            SET legal_name = $1, customer_type = $2::customer_type, currency_code = $3,
+                credit_limit = $4, payment_terms = $5, tax_exempt = $6, tax_jurisdictions = $7,
+                primary_contact_id = COALESCE(primary_contact_id, $8),
+                modified_by = $9, modified_at = $10
+            WHERE id = $11 AND tenant_id = $12
+            "#,
+        )
+        .bind(legal_name)
+        .bind(customer_type.clone())
+        .bind(currency_code)
+        .bind(credit_limit)
+        .bind(payment_terms.as_ref().map(serde_json::to_value).transpose()?)
+        .bind(tax_exempt)
+        .bind(serde_json::to_value(&tax_jurisdictions)?)
+        .bind(source_customer.primary_contact_id)
+        .bind(merged_by)
+        .bind(merged_at)
+        .bind(target)
+        .bind(tenant_id)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            UPDATE customers
+            SET is_deleted = true, deleted_by = $1, deleted_at = $2, primary_contact_id = NULL,
+                tax_jurisdictions = '[]', modified_by = $1, modified_at = $2
+            WHERE id = $3 AND tenant_id = $4
+            "#,
+        )
+        .bind(merged_by)
+        .bind(merged_at)
+        .bind(source)
+        .bind(tenant_id)
+        .execute(&mut *tx)
+        .await?;
+
+        let events = PostgresCustomerEventStore::new(self.pool.clone(), self.tenant_context.clone());
+        let deleted = CustomerEvent::CustomerSoftDeleted {
+            customer_id: source,
+            reason: format!("Merged into customer {}", target_customer.customer_number),
+            deleted_by: merged_by,
+            deleted_at: *merged_at,
+        };
+        events.append_events_on(&mut tx, source, vec![deleted], None, Some(merged_by)).await?;
+        events.append_events_on(&mut tx, target, vec![merged.clone()], None, Some(merged_by)).await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO customer_merges
+                (tenant_id, source_customer_id, target_customer_id, strategy, fields_from_source,
+                 reassigned, merged_by, merged_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            "#,
+        )
+        .bind(tenant_id)
+        .bind(source)
+        .bind(target)
+        .bind(serde_json::to_value(strategy)?)
+        .bind(serde_json::to_value(fields_from_source)?)
+        .bind(serde_json::to_value(&reassigned)?)
+        .bind(merged_by)
+        .bind(merged_at)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        self.get_customer_by_id(target)
+            .await?
+            .ok_or(MasterDataError::CustomerNotFound { id: target.to_string() })
+    }
+}
+
+/// Storage for the customer communication log
+#[async_trait]
+pub trait CommunicationRepository: Send + Sync {
+    async fn customer_exists(&self, tenant_id: Uuid, customer_id: Uuid) -> Result<bool>;
+    async fn insert_communication(&self, entry: &CustomerCommunication) -> Result<()>;
+    async fn get_communication(&self, tenant_id: Uuid, id: Uuid) -> Result<Option<CustomerCommunication>>;
+    /// Newest first; with `visible_author` only that user's and system-generated entries
+    async fn list_communications(
+        &self,
+        tenant_id: Uuid,
+        customer_id: Uuid,
+        visible_author: Option<Uuid>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<CustomerCommunication>>;
+    async fn update_communication(&self, entry: &CustomerCommunication) -> Result<()>;
+    async fn delete_communication(&self, tenant_id: Uuid, id: Uuid) -> Result<bool>;
+    /// Open follow-ups of one user due before `due_before`, across customers
+    async fn open_follow_ups_for_user(
+        &self,
+        tenant_id: Uuid,
+        user_id: Uuid,
+        due_before: DateTime<Utc>,
+    ) -> Result<Vec<CustomerCommunication>>;
+    /// Open follow-ups of all tenants due before `due_before`, for reminders
+    async fn open_follow_ups_due(&self, due_before: DateTime<Utc>) -> Result<Vec<CustomerCommunication>>;
+}
+
+/// PostgreSQL implementation of the communication log
+pub struct PostgresCommunicationRepository {
+    pool: PgPool,
+}
+
+impl PostgresCommunicationRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    const COLUMNS: &'static str = "id, tenant_id, customer_id, communication_type, direction, subject, body, \
+        occurred_at, logged_by, is_system_generated, follow_up_due, follow_up_done, created_at, updated_at";
+
+    fn row_to_communication(row: &sqlx::postgres::PgRow) -> Result<CustomerCommunication> {
+        let communication_type: String = row.try_get("communication_type")?;
+        let direction: String = row.try_get("direction")?;
+
+        Ok(CustomerCommunication {
+            id: row.try_get("id")?,
+            tenant_id: row.try_get("tenant_id")?,
+            customer_id: row.try_get("customer_id")?,
+            communication_type: CommunicationType::parse(&communication_type).ok_or_else(|| {
+                MasterDataError::DatabaseError(format!("Unknown communication type: {}", communication_type))
+            })?,
+            direction: CommunicationDirection::parse(&direction).ok_or_else(|| {
+                MasterDataError::DatabaseError(format!("Unknown communication direction: {}", direction))
+            })?,
+            subject: row.try_get("subject")?,
+            body: row.try_get("body")?,
+            occurred_at: row.try_get("occurred_at")?,
+            logged_by: row.try_get("logged_by")?,
+            is_system_generated: row.try_get("is_system_generated")?,
+            follow_up_due: row.try_get("follow_up_due")?,
+            follow_up_done: row.try_get("follow_up_done")?,
+            created_at: row.try_get("created_at")?,
+            updated_at: row.try_get("updated_at")?,
+        })
+    }
+}
+
+#[async_trait]
+impl CommunicationRepository for PostgresCommunicationRepository {
+    async fn customer_exists(&self, tenant_id: Uuid, customer_id: Uuid) -> Result<bool> {
+        let row = sqlx::query(
+            "SELECT EXISTS(SELECT 1 FROM customers WHERE tenant_id = $1 AND id = $2 AND is_deleted = false) AS found",
+        )
+        .bind(tenant_id)
+        .bind(customer_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(row.try_get("found")?)
+    }
+
+    async fn insert_communication(&self, entry: &CustomerCommunication) -> Result<()> {
+        sqlx::query(&format!(
+            "INSERT INTO public.customer_communications ({}) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)",
+            Self::COLUMNS
+        ))
+        .bind(entry.id)
+        .bind(entry.tenant_id)
+        .bind(entry.customer_id)
+        .bind(entry.communication_type.as_str())
+        .bind(entry.direction.as_str())
+        .bind(&entry.subject)
+        .bind(&entry.body)
+        .bind(entry.occurred_at)
+        .bind(entry.logged_by)
+        .bind(entry.is_system_generated)
+        .bind(entry.follow_up_due)
+        .bind(entry.follow_up_done)
+        .bind(entry.created_at)
+        .bind(entry.updated_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_communication(&self, tenant_id: Uuid, id: Uuid) -> Result<Option<CustomerCommunication>> {
+        let row = sqlx::query(&format!(
+            "SELECT {} FROM public.customer_communications WHERE tenant_id = $1 AND id = $2",
+            Self::COLUMNS
+        ))
+        .bind(tenant_id)
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.as_ref().map(Self::row_to_communication).transpose()
+    }
+
+    async fn list_communications(
+        &self,
+        tenant_id: Uuid,
+        customer_id: Uuid,
+        visible_author: Option<Uuid>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<CustomerCommunication>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM public.customer_communications \
+             WHERE tenant_id = $1 AND customer_id = $2 \
+               AND ($3::uuid IS NULL OR logged_by = $3 OR is_system_generated) \
+             ORDER BY occurred_at DESC, created_at DESC \
+             LIMIT $4 OFFSET $5",
+            Self::COLUMNS
+        ))
+        .bind(tenant_id)
+        .bind(customer_id)
+        .bind(visible_author)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(Self::row_to_communication).collect()
+    }
+
+    async fn update_communication(&self, entry: &CustomerCommunication) -> Result<()> {
+        let result = sqlx::query(
+            "UPDATE public.customer_communications \
+             SET subject = $3, body = $4, occurred_at = $5, follow_up_due = $6, follow_up_done = $7, updated_at = $8 \
+             WHERE tenant_id = $1 AND id = $2",
+        )
+        .bind(entry.tenant_id)
+        .bind(entry.id)
+        .bind(&entry.subject)
+        .bind(&entry.body)
+        .bind(entry.occurred_at)
+        .bind(entry.follow_up_due)
+        .bind(entry.follow_up_done)
+        .bind(entry.updated_at)
+        .execute(&self.pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(MasterDataError::CommunicationNotFound { id: entry.id.to_string() });
+        }
+        Ok(())
+    }
+
+    async fn delete_communication(&self, tenant_id: Uuid, id: Uuid) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM public.customer_communications WHERE tenant_id = $1 AND id = $2")
+            .bind(tenant_id)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn open_follow_ups_for_user(
+        &self,
+        tenant_id: Uuid,
+        user_id: Uuid,
+        due_before: DateTime<Utc>,
+    ) -> Result<Vec<CustomerCommunication>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM public.customer_communications \
+             WHERE tenant_id = $1 AND logged_by = $2 \
+               AND follow_up_done = false AND follow_up_due <= $3 \
+             ORDER BY follow_up_due",
+            Self::COLUMNS
+        ))
+        .bind(tenant_id)
+        .bind(user_id)
+        .bind(due_before)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(Self::row_to_communication).collect()
+    }
+
+    async fn open_follow_ups_due(&self, due_before: DateTime<Utc>) -> Result<Vec<CustomerCommunication>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM public.customer_communications \
+             WHERE logged_by IS NOT NULL AND follow_up_done = false AND follow_up_due <= $1 \
+             ORDER BY tenant_id, logged_by, follow_up_due",
+            Self::COLUMNS
+        ))
+        .bind(due_before)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(Self::row_to_communication).collect()
+    }
+}
+
+/// Territory assignment stored on a customer row, see [`territory`](super::territory)
+pub(crate) fn customer_assignment(row: &PgRow) -> CustomerAssignment {
+    let uuid = |column: &str| row.try_get::<Option<Uuid>, _>(column).ok().flatten();
+    CustomerAssignment {
+        territory_id: uuid("territory_id"),
+        sales_representative: Assignee::resolve(
+            uuid("derived_sales_representative_id"),
+            uuid("sales_representative_override_id"),
+        ),
+        account_manager: Assignee::resolve(uuid("derived_account_manager_id"), uuid("account_manager_override_id")),
+    }
+}
+
+/// Storage for sales territories and the assignments derived from them
+#[async_trait]
+pub trait TerritoryRepository: Send + Sync {
+    /// Territories of the tenant by descending priority
+    async fn list_territories(&self, tenant_id: Uuid) -> Result<Vec<Territory>>;
+    async fn get_territory(&self, tenant_id: Uuid, id: Uuid) -> Result<Option<Territory>>;
+    async fn insert_territory(&self, territory: &Territory) -> Result<()>;
+    async fn update_territory(&self, territory: &Territory) -> Result<()>;
+    /// Customers of a deleted territory keep their assignees until the next re-assignment
+    async fn delete_territory(&self, tenant_id: Uuid, id: Uuid) -> Result<bool>;
+    /// Every customer of the tenant with what the rules see and its stored assignment
+    async fn customer_ownerships(&self, tenant_id: Uuid) -> Result<Vec<CustomerOwnership>>;
+    async fn customer_ownership(&self, tenant_id: Uuid, customer_id: Uuid) -> Result<Option<CustomerOwnership>>;
+    /// Store the new assignments with their ownership changes in one transaction.
+    /// Customers whose stored assignment no longer equals `before` were changed
+    /// meanwhile and are skipped; returns the assignments stored.
+    async fn store_assignments(
+        &self,
+        tenant_id: Uuid,
+        assignments: &[PlannedAssignment],
+        changes: &[OwnershipChange],
+    ) -> Result<usize>;
+}
+
+/// PostgreSQL implementation of the territory storage
+pub struct PostgresTerritoryRepository {
+    pool: PgPool,
+}
+
+impl PostgresTerritoryRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    const COLUMNS: &'static str = "id, tenant_id, name, description, priority, rules, sales_representative_id, \
+        account_manager_id, is_active, created_by, created_at, updated_at";
+
+    const OWNERSHIP_QUERY: &'static str = r#"
+        SELECT c.id, c.industry_classification, c.business_size, c.territory_id,
+               c.derived_sales_representative_id, c.derived_account_manager_id,
+               c.sales_representative_override_id, c.account_manager_override_id,
+               a.country_code, a.state_province, a.postal_code
+        FROM customers c
+        LEFT JOIN LATERAL (
+            SELECT country_code, state_province, postal_code
+            FROM addresses
+            WHERE entity_type = 'customer' AND entity_id = c.id AND is_active
+            ORDER BY (id = c.primary_address_id) DESC, is_primary DESC, created_at, id
+            LIMIT 1
+        ) a ON TRUE
+        WHERE c.tenant_id = $1 AND c.is_deleted = false AND ($2::uuid IS NULL OR c.id = $2)
+        ORDER BY c.id
+        "#;
+
+    fn row_to_territory(row: &PgRow) -> Result<Territory> {
+        Ok(Territory {
+            id: row.try_get("id")?,
+            tenant_id: row.try_get("tenant_id")?,
+            name: row.try_get("name")?,
+            description: row.try_get("description")?,
+            priority: row.try_get("priority")?,
+            rules: serde_json::from_value(row.try_get("rules")?)?,
+            sales_representative_id: row.try_get("sales_representative_id")?,
+            account_manager_id: row.try_get("account_manager_id")?,
+            is_active: row.try_get("is_active")?,
+            created_by: row.try_get("created_by")?,
+            created_at: row.try_get("created_at")?,
+            updated_at: row.try_get("updated_at")?,
+        })
+    }
+
+    fn row_to_ownership(row: &PgRow) -> Result<CustomerOwnership> {
+        Ok(CustomerOwnership {
+            profile: CustomerProfile {
+                customer_id: row.try_get("id")?,
+                country_code: row.try_get("country_code")?,
+                region: row.try_get("state_province")?,
+                postal_code: row.try_get("postal_code")?,
+                industry: row.try_get::<Option<IndustryClassification>, _>("industry_classification").ok().flatten(),
+                size: row.try_get::<Option<BusinessSize>, _>("business_size").ok().flatten(),
+            },
+            assignment: customer_assignment(row),
+        })
+    }
+
+    fn duplicate_name(error: sqlx::Error, name: &str) -> MasterDataError {
+        match &error {
+            sqlx::Error::Database(db) if db.is_unique_violation() => MasterDataError::ValidationError {
+                field: "name".to_string(),
+                message: format!("A territory named {} already exists", name),
+            },
+            _ => error.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl TerritoryRepository for PostgresTerritoryRepository {
+    async fn list_territories(&self, tenant_id: Uuid) -> Result<Vec<Territory>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM public.sales_territories WHERE tenant_id = $1 ORDER BY priority DESC, created_at, id",
+            Self::COLUMNS
+        ))
+        .bind(tenant_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(Self::row_to_territory).collect()
+    }
+
+    async fn get_territory(&self, tenant_id: Uuid, id: Uuid) -> Result<Option<Territory>> {
+        let row = sqlx::query(&format!(
+            "SELECT {} FROM public.sales_territories WHERE tenant_id = $1 AND id = $2",
+            Self::COLUMNS
+        ))
+        .bind(tenant_id)
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.as_ref().map(Self::row_to_territory).transpose()
+    }
+
+    async fn insert_territory(&self, territory: &Territory) -> Result<()> {
+        sqlx::query(&format!(
+            "INSERT INTO public.sales_territories ({}) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)",
+            Self::COLUMNS
+        ))
+        .bind(territory.id)
+        .bind(territory.tenant_id)
+        .bind(&territory.name)
+        .bind(&territory.description)
+        .bind(territory.priority)
+        .bind(serde_json::to_value(&territory.rules)?)
+        .bind(territory.sales_representative_id)
+        .bind(territory.account_manager_id)
+        .bind(territory.is_active)
+        .bind(territory.created_by)
+        .bind(territory.created_at)
+        .bind(territory.updated_at)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| Self::duplicate_name(e, &territory.name))?;
+
+        Ok(())
+    }
+
+    async fn update_territory(&self, territory: &Territory) -> Result<()> {
+        let result = sqlx::query(
+            "UPDATE public.sales_territories \
+             SET name = $3, description = $4, priority = $5, rules = $6, sales_representative_id = $7, \
+                 account_manager_id = $8, is_active = $9, updated_at = $10 \
+             WHERE tenant_id = $1 AND id = $2",
+        )
+        .bind(territory.tenant_id)
+        .bind(territory.id)
+        .bind(&territory.name)
+        .bind(&territory.description)
+        .bind(territory.priority)
+        .bind(serde_json::to_value(&territory.rules)?)
+        .bind(territory.sales_representative_id)
+        .bind(territory.account_manager_id)
+        .bind(territory.is_active)
+        .bind(territory.updated_at)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| Self::duplicate_name(e, &territory.name))?;
+
+        if result.rows_affected() == 0 {
+            return Err(MasterDataError::TerritoryNotFound { id: territory.id.to_string() });
+        }
+        Ok(())
+    }
+
+    async fn delete_territory(&self, tenant_id: Uuid, id: Uuid) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM public.sales_territories WHERE tenant_id = $1 AND id = $2")
+            .bind(tenant_id)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn customer_ownerships(&self, tenant_id: Uuid) -> Result<Vec<CustomerOwnership>> {
+        let rows = sqlx::query(Self::OWNERSHIP_QUERY)
+            .bind(tenant_id)
+            .bind(Option::<Uuid>::None)
+            .fetch_all(&self.pool)
+            .await?;
+
+        rows.iter().map(Self::row_to_ownership).collect()
+    }
+
+    async fn customer_ownership(&self, tenant_id: Uuid, customer_id: Uuid) -> Result<Option<CustomerOwnership>> {
+        let row = sqlx::query(Self::OWNERSHIP_QUERY)
+            .bind(tenant_id)
+            .bind(Some(customer_id))
+            .fetch_optional(&self.pool)
+            .await?;
+
+        row.as_ref().map(Self::row_to_ownership).transpose()
+    }
+
+    async fn store_assignments(
+        &self,
+        tenant_id: Uuid,
+        assignments: &[PlannedAssignment],
+        changes: &[OwnershipChange],
+    ) -> Result<usize> {
+        let mut tx = self.pool.begin().await?;
+        let mut stored = 0;
+
+        for planned in assignments {
+            let (before, after) = (&planned.before, &planned.after);
+            let result = sqlx::query(
+                r#"
+                UPDATE customers
+                SET territory_id = $3,
+                    derived_sales_representative_id = $4, derived_account_manager_id = $5,
+                    sales_representative_override_id = $6, account_manager_override_id = $7,
+                    sales_representative_id = $8, account_manager_id = $9
+                WHERE tenant_id = $1 AND id = $2 AND is_deleted = false
+                  AND territory_id IS NOT DISTINCT FROM $10
+                  AND derived_sales_representative_id IS NOT DISTINCT FROM $11
+                  AND derived_account_manager_id IS NOT DISTINCT FROM $12
+                  AND sales_representative_override_id IS NOT DISTINCT FROM $13
+                  AND account_manager_override_id IS NOT DISTINCT FROM $14
+                "#,
+            )
+            .bind(tenant_id)
+            .bind(planned.customer_id)
+            .bind(after.territory_id)
+            .bind(after.sales_representative.derived_id)
+            .bind(after.account_manager.derived_id)
+            .bind(after.sales_representative.override_id)
+            .bind(after.account_manager.override_id)
+            .bind(after.sales_representative.assignee_id)
+            .bind(after.account_manager.assignee_id)
+            .bind(before.territory_id)
+            .bind(before.sales_representative.derived_id)
+            .bind(before.account_manager.derived_id)
+            .bind(before.sales_representative.override_id)
+            .bind(before.account_manager.override_id)
+            .execute(&mut *tx)
+            .await?;
+            if result.rows_affected() == 0 {
+                continue;
+            }
+            stored += 1;
+
+            for change in changes.iter().filter(|change| change.customer_id == planned.customer_id) {
+                sqlx::query(
+                    r#"
+                    INSERT INTO public.customer_ownership_changes
+                        (id, tenant_id, customer_id, role, previous_assignee_id, new_assignee_id,
+                         source, territory_id, changed_by, changed_at)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+                    "#,
+                )
+                .bind(change.id)
+                .bind(change.tenant_id)
+                .bind(change.customer_id)
+                .bind(change.role.as_str())
+                .bind(change.previous_assignee_id)
+                .bind(change.new_assignee_id)
+                .bind(change.source.as_str())
+                .bind(change.territory_id)
+                .bind(change.changed_by)
+                .bind(change.changed_at)
+                .execute(&mut *tx)
+                .await?;
+            }
+        }
+
+        tx.commit().await?;
+        Ok(stored)
+    }
+}
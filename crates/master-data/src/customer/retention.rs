@@ -0,0 +1,158 @@
+//! # Customer Data Retention
+//!
+//! Retention categories owned by the customer module:
+//!
+//! - `customer_communications`: communication log entries, deleted or
+//!   anonymized (subject and body replaced, the entry itself kept for counts).
+//! - `deleted_customers`: soft-deleted customers, purged once `deleted_at`
+//!   is past the retention period.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use erp_core::retention::{RetentionAction, RetentionCategory, RetentionHandler};
+use erp_core::{Error, ErrorCode, Result};
+use sqlx::{Pool, Postgres};
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Placeholder subject of anonymized communication entries
+pub const ANONYMIZED_SUBJECT: &str = "[anonymized]";
+
+pub struct CommunicationRetention {
+    pool: Pool<Postgres>,
+}
+
+impl CommunicationRetention {
+    pub fn new(pool: Pool<Postgres>) -> Self {
+        Self { pool }
+    }
+
+    /// Three years, deleted
+    pub fn category(pool: Pool<Postgres>) -> RetentionCategory {
+        RetentionCategory {
+            name: "customer_communications".to_string(),
+            description: "Customer communication log entries by occurrence date".to_string(),
+            default_days: 3 * 365,
+            default_action: RetentionAction::Delete,
+            supported_actions: vec![RetentionAction::Delete, RetentionAction::Anonymize],
+            handler: Arc::new(Self::new(pool)),
+        }
+    }
+}
+
+#[async_trait]
+impl RetentionHandler for CommunicationRetention {
+    async fn count_expired(&self, tenant_id: Uuid, cutoff: DateTime<Utc>, action: RetentionAction) -> Result<u64> {
+        let count: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM public.customer_communications \
+             WHERE tenant_id = $1 AND occurred_at < $2 AND ($3 = 'delete' OR anonymized_at IS NULL)",
+        )
+        .bind(tenant_id)
+        .bind(cutoff)
+        .bind(action.as_str())
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(count as u64)
+    }
+
+    async fn enforce_batch(
+        &self,
+        tenant_id: Uuid,
+        cutoff: DateTime<Utc>,
+        action: RetentionAction,
+        limit: i64,
+    ) -> Result<u64> {
+        let sql = match action {
+            RetentionAction::Delete => {
+                "DELETE FROM public.customer_communications WHERE id IN ( \
+                     SELECT id FROM public.customer_communications \
+                     WHERE tenant_id = $1 AND occurred_at < $2 ORDER BY occurred_at LIMIT $3)"
+            }
+            RetentionAction::Anonymize => {
+                "UPDATE public.customer_communications \
+                 SET subject = $4, body = NULL, anonymized_at = NOW(), updated_at = NOW() \
+                 WHERE id IN ( \
+                     SELECT id FROM public.customer_communications \
+                     WHERE tenant_id = $1 AND occurred_at < $2 AND anonymized_at IS NULL \
+                     ORDER BY occurred_at LIMIT $3)"
+            }
+            RetentionAction::Archive => {
+                return Err(Error::new(
+                    ErrorCode::ValidationFailed,
+                    "Customer communications cannot be archived",
+                ))
+            }
+        };
+
+        let mut query = sqlx::query(sql).bind(tenant_id).bind(cutoff).bind(limit);
+        if action == RetentionAction::Anonymize {
+            query = query.bind(ANONYMIZED_SUBJECT);
+        }
+        let result = query.execute(&self.pool).await?;
+        Ok(result.rows_affected())
+    }
+}
+
+pub struct DeletedCustomerPurge {
+    pool: Pool<Postgres>,
+}
+
+impl DeletedCustomerPurge {
+    pub fn new(pool: Pool<Postgres>) -> Self {
+        Self { pool }
+    }
+
+    /// 180 days after the soft delete, purged
+    pub fn category(pool: Pool<Postgres>) -> RetentionCategory {
+        RetentionCategory {
+            name: "deleted_customers".to_string(),
+            description: "Soft-deleted customers by deletion date".to_string(),
+            default_days: 180,
+            default_action: RetentionAction::Delete,
+            supported_actions: vec![RetentionAction::Delete],
+            handler: Arc::new(Self::new(pool)),
+        }
+    }
+}
+
+#[async_trait]
+impl RetentionHandler for DeletedCustomerPurge {
+    async fn count_expired(&self, tenant_id: Uuid, cutoff: DateTime<Utc>, _action: RetentionAction) -> Result<u64> {
+        let count: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM customers WHERE tenant_id = $1 AND is_deleted = true AND deleted_at < $2",
+        )
+        .bind(tenant_id)
+        .bind(cutoff)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(count as u64)
+    }
+
+    async fn enforce_batch(
+        &self,
+        tenant_id: Uuid,
+        cutoff: DateTime<Utc>,
+        action: RetentionAction,
+        limit: i64,
+    ) -> Result<u64> {
+        if action != RetentionAction::Delete {
+            return Err(Error::new(
+                ErrorCode::ValidationFailed,
+                "Soft-deleted customers can only be purged",
+            ));
+        }
+
+        let result = sqlx::query(
+            "DELETE FROM customers WHERE id IN ( \
+                 SELECT id FROM customers \
+                 WHERE tenant_id = $1 AND is_deleted = true AND deleted_at < $2 \
+                 ORDER BY deleted_at LIMIT $3)",
+        )
+        .bind(tenant_id)
+        .bind(cutoff)
+        .bind(limit)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected())
+    }
+}
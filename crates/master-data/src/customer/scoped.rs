@@ -0,0 +1,218 @@
+//! Customer data seen through a portal scope
+//!
+//! [`ScopedCustomerRepository`] wraps the repository of a request made with a
+//! customer portal token (see [`erp_core::portal`]). Reads only return the
+//! customers of the scope's [`CustomerVisibility`]; other customers look as if
+//! they did not exist. Writes are refused.
+
+use async_trait::async_trait;
+use uuid::Uuid;
+
+use crate::customer::bulk_import::{BulkImportOptions, BulkImportResult};
+use crate::customer::field_encryption::MaskedFinancialIdentifiers;
+use crate::customer::cursor::DEFAULT_CURSOR_PAGE_SIZE;
+use crate::customer::repository::CustomerRepository;
+use crate::customer::*;
+use crate::error::{MasterDataError, Result};
+use crate::types::*;
+use erp_core::portal::{CustomerVisibility, PortalScope};
+
+pub struct ScopedCustomerRepository {
+    inner: Box<dyn CustomerRepository>,
+    scope: PortalScope,
+}
+
+impl ScopedCustomerRepository {
+    pub fn new(inner: Box<dyn CustomerRepository>, scope: PortalScope) -> Self {
+        Self { inner, scope }
+    }
+
+    /// Resolved per call, so hierarchy changes apply right away
+    async fn visibility(&self) -> Result<CustomerVisibility> {
+        let hierarchy = if self.scope.include_children {
+            self.inner
+                .get_customer_hierarchy(self.scope.customer_id)
+                .await?
+                .iter()
+                .map(|customer| (customer.id, customer.parent_customer_id))
+                .collect()
+        } else {
+            Vec::new()
+        };
+        Ok(CustomerVisibility::resolve(&self.scope, &hierarchy))
+    }
+
+    async fn visible(&self, mut customers: Vec<Customer>) -> Result<Vec<Customer>> {
+        self.visibility().await?.retain(&mut customers, |customer| customer.id);
+        Ok(customers)
+    }
+
+    async fn allows(&self, customer_id: Uuid) -> Result<bool> {
+        Ok(self.visibility().await?.allows(customer_id))
+    }
+
+    /// The visible customers matching the search term and customer numbers
+    async fn matching(&self, criteria: &CustomerSearchCriteria) -> Result<Vec<Customer>> {
+        let term = criteria.search_term.as_deref().map(str::to_lowercase);
+        let mut customers = Vec::new();
+        for id in self.visibility().await?.customer_ids() {
+            let Some(customer) = self.inner.get_customer_by_id(id).await? else {
+                continue;
+            };
+            let matches_term = term.as_deref().is_none_or(|term| {
+                customer.legal_name.to_lowercase().contains(term)
+                    || customer.customer_number.to_lowercase().contains(term)
+            });
+            let matches_number = criteria
+                .customer_numbers
+                .as_ref()
+                .is_none_or(|numbers| numbers.contains(&customer.customer_number));
+            if matches_term && matches_number {
+                customers.push(customer);
+            }
+        }
+        customers.sort_by(|a, b| a.legal_name.cmp(&b.legal_name).then(a.id.cmp(&b.id)));
+        Ok(customers)
+    }
+
+    fn read_only(action: &str) -> MasterDataError {
+        MasterDataError::PermissionDenied {
+            action: format!("{} with a customer portal account", action),
+        }
+    }
+}
+
+#[async_trait]
+impl CustomerRepository for ScopedCustomerRepository {
+    async fn create_customer(&self, _request: &CreateCustomerRequest, _created_by: Uuid) -> Result<Customer> {
+        Err(Self::read_only("create customers"))
+    }
+
+    async fn bulk_create_customers(
+        &self,
+        _requests: Vec<CreateCustomerRequest>,
+        _options: &BulkImportOptions,
+        _created_by: Uuid,
+    ) -> Result<BulkImportResult> {
+        Err(Self::read_only("create customers"))
+    }
+
+    async fn get_customer_by_id(&self, id: Uuid) -> Result<Option<Customer>> {
+        if !self.allows(id).await? {
+            return Ok(None);
+        }
+        self.inner.get_customer_by_id(id).await
+    }
+
+    async fn get_customer_by_number(&self, customer_number: &str) -> Result<Option<Customer>> {
+        match self.inner.get_customer_by_number(customer_number).await? {
+            Some(customer) if self.allows(customer.id).await? => Ok(Some(customer)),
+            _ => Ok(None),
+        }
+    }
+
+    async fn update_customer(&self, _id: Uuid, _update: &UpdateCustomerRequest, _modified_by: Uuid) -> Result<Customer> {
+        Err(Self::read_only("update customers"))
+    }
+
+    async fn delete_customer(&self, _id: Uuid, _deleted_by: Uuid) -> Result<()> {
+        Err(Self::read_only("delete customers"))
+    }
+
+    async fn list_customers(&self, criteria: &CustomerSearchCriteria, page: u32, page_size: u32) -> Result<CustomerSearchResponse> {
+        let customers = self.matching(criteria).await?;
+        let total_count = customers.len() as u64;
+        let page_size = page_size.max(1);
+        let offset = (page.saturating_sub(1) as usize).saturating_mul(page_size as usize);
+        Ok(CustomerSearchResponse {
+            customers: customers.into_iter().skip(offset).take(page_size as usize).collect(),
+            total_count,
+            page,
+            page_size,
+            total_pages: total_count.div_ceil(page_size as u64) as u32,
+            next_cursor: None,
+        })
+    }
+
+    async fn get_customer_hierarchy(&self, customer_id: Uuid) -> Result<Vec<Customer>> {
+        if !self.allows(customer_id).await? {
+            return Ok(Vec::new());
+        }
+        let hierarchy = self.inner.get_customer_hierarchy(customer_id).await?;
+        self.visible(hierarchy).await
+    }
+
+    async fn get_customers_by_corporate_group(&self, group_id: Uuid) -> Result<Vec<Customer>> {
+        let customers = self.inner.get_customers_by_corporate_group(group_id).await?;
+        self.visible(customers).await
+    }
+
+    async fn get_customer_addresses(&self, customer_id: Uuid) -> Result<Vec<Address>> {
+        if !self.allows(customer_id).await? {
+            return Ok(Vec::new());
+        }
+        self.inner.get_customer_addresses(customer_id).await
+    }
+
+    async fn get_customer_contacts(&self, customer_id: Uuid) -> Result<Vec<ContactInfo>> {
+        if !self.allows(customer_id).await? {
+            return Ok(Vec::new());
+        }
+        self.inner.get_customer_contacts(customer_id).await
+    }
+
+    async fn search_customers(&self, criteria: &CustomerSearchCriteria) -> Result<Vec<Customer>> {
+        let page = criteria.page.unwrap_or(1);
+        let page_size = criteria.page_size.unwrap_or(50);
+        Ok(self.list_customers(criteria, page, page_size).await?.customers)
+    }
+
+    /// Always by legal name, as [`list_customers`](Self::list_customers) sorts
+    async fn search_customers_page(&self, criteria: &CustomerSearchCriteria) -> Result<CustomerPage> {
+        let order = CustomerOrder::default();
+        let after = CustomerCursor::of(criteria, order)?;
+        let customers = self.matching(criteria).await?;
+        let (skip, page_size) = match &after {
+            Some(after) => (
+                customers
+                    .iter()
+                    .take_while(|customer| (customer.legal_name.as_str(), customer.id) <= (after.key.as_str(), after.id))
+                    .count(),
+                criteria.page_size.unwrap_or(DEFAULT_CURSOR_PAGE_SIZE).max(1) as usize,
+            ),
+            None => {
+                let page_size = criteria.page_size.unwrap_or(50).max(1) as usize;
+                ((criteria.page.unwrap_or(1).saturating_sub(1) as usize).saturating_mul(page_size), page_size)
+            }
+        };
+        let more = customers.len() > skip.saturating_add(page_size);
+        let customers: Vec<Customer> = customers.into_iter().skip(skip).take(page_size).collect();
+        let next_cursor = customers
+            .last()
+            .filter(|_| more)
+            .map(|last| CustomerCursor::new(order, last.legal_name.as_str(), last.id).encode());
+        Ok(CustomerPage { customers, next_cursor })
+    }
+
+    async fn is_customer_number_available(&self, _customer_number: &str) -> Result<bool> {
+        // Would tell the portal account which numbers other customers use
+        Err(Self::read_only("check customer numbers"))
+    }
+
+    async fn find_customers_by_tax_number(&self, tax_number: &str) -> Result<Vec<Customer>> {
+        let customers = self.inner.find_customers_by_tax_number(tax_number).await?;
+        self.visible(customers).await
+    }
+
+    async fn find_customers_by_iban(&self, iban: &str) -> Result<Vec<Customer>> {
+        let customers = self.inner.find_customers_by_iban(iban).await?;
+        self.visible(customers).await
+    }
+
+    async fn get_masked_financial_identifiers(&self, customer_id: Uuid) -> Result<Option<MaskedFinancialIdentifiers>> {
+        if !self.allows(customer_id).await? {
+            return Ok(None);
+        }
+        self.inner.get_masked_financial_identifiers(customer_id).await
+    }
+}
@@ -6,6 +6,7 @@ use sqlx::Row;
 use rust_decimal::Decimal;
 
 use crate::customer::model::*;
+use crate::customer::repository::customer_assignment;
 use crate::types::{IndustryClassification, RiskRating, FinancialInfo, AuditFields, SyncInfo};
 use crate::error::Result;
 
@@ -357,11 +358,13 @@ impl AdvancedSearchEngine {
                     payment_terms: None,
                     tax_exempt: row.try_get::<bool, _>("tax_exempt").ok().unwrap_or(false),
                     tax_numbers: HashMap::new(),
+                    bank_accounts: Vec::new(),
                 },
                 price_group_id: row.try_get::<Option<uuid::Uuid>, _>("price_group_id").ok().flatten(),
                 discount_group_id: row.try_get::<Option<uuid::Uuid>, _>("discount_group_id").ok().flatten(),
                 sales_representative_id: row.try_get::<Option<uuid::Uuid>, _>("sales_representative_id").ok().flatten(),
                 account_manager_id: row.try_get::<Option<uuid::Uuid>, _>("account_manager_id").ok().flatten(),
+                assignment: customer_assignment(&row),
                 customer_segments: vec![],
                 acquisition_channel: row.try_get::<Option<AcquisitionChannel>, _>("acquisition_channel").ok().flatten(),
                 customer_lifetime_value: row.try_get::<Option<rust_decimal::Decimal>, _>("customer_lifetime_value").ok().flatten(),
@@ -1,10 +1,17 @@
 use async_trait::async_trait;
+use std::sync::Arc;
+use tracing::Instrument;
 use uuid::Uuid;
 use validator::Validate;
 
+use crate::customer::bulk_import::{validate_row, BulkImportError, BulkImportOptions, BulkImportResult, BulkImportRow};
 use crate::customer::model::*;
 use crate::customer::repository::CustomerRepository;
+use crate::customer::validation::{warn_unverifiable_tax_numbers, CustomerValidator};
 use crate::error::{MasterDataError, Result};
+use crate::spans;
+use erp_core::latency::LatencyBudget;
+use erp_core::plan_limits::{PlanLimitGuard, PlanResource};
 use erp_core::TenantContext;
 
 /// Business rules and validation for customer operations
@@ -13,6 +20,14 @@ pub trait CustomerService: Send + Sync {
     /// Create a new customer with full business validation
     async fn create_customer(&self, request: CreateCustomerRequest, created_by: Uuid) -> Result<Customer>;
 
+    /// Create many customers at once, reporting each row's outcome instead of failing the call
+    async fn bulk_create_customers(
+        &self,
+        requests: Vec<CreateCustomerRequest>,
+        options: BulkImportOptions,
+        created_by: Uuid,
+    ) -> Result<BulkImportResult>;
+
     /// Update an existing customer with business rule validation
     async fn update_customer(&self, id: Uuid, request: UpdateCustomerRequest, modified_by: Uuid) -> Result<Customer>;
 
@@ -45,6 +60,8 @@ pub trait CustomerService: Send + Sync {
 pub struct DefaultCustomerService {
     repository: Box<dyn CustomerRepository>,
     tenant_context: TenantContext,
+    plan_limits: Option<Arc<dyn PlanLimitGuard>>,
+    latency: LatencyBudget,
 }
 
 impl DefaultCustomerService {
@@ -52,13 +69,29 @@ impl DefaultCustomerService {
         Self {
             repository,
             tenant_context,
+            plan_limits: None,
+            latency: LatencyBudget::default(),
         }
     }
+
+    /// Refuse new customers once the tenant is at the customer limit of its plan
+    pub fn with_plan_limits(mut self, plan_limits: Arc<dyn PlanLimitGuard>) -> Self {
+        self.plan_limits = Some(plan_limits);
+        self
+    }
+
+    /// Time methods against these budgets instead of the default ones
+    pub fn with_latency_budget(mut self, latency: LatencyBudget) -> Self {
+        self.latency = latency;
+        self
+    }
 }
 
 #[async_trait]
 impl CustomerService for DefaultCustomerService {
-    async fn create_customer(&self, request: CreateCustomerRequest, created_by: Uuid) -> Result<Customer> {
+    #[tracing::instrument(skip_all, fields(tenant_id = %self.tenant_context.tenant_id))]
+    async fn create_customer(&self, mut request: CreateCustomerRequest, created_by: Uuid) -> Result<Customer> {
+        let _latency = self.latency.start("customer.write", "create_customer");
         // 1. Check tenant context permissions
         if !self.tenant_context.has_permission("customer:create") {
             return Err(MasterDataError::ValidationError {
@@ -74,8 +107,14 @@ impl CustomerService for DefaultCustomerService {
                 message: e.to_string(),
             })?;
 
-        // 3. Business rule validation
+        // 3. Business rule validation, on normalized addresses so they are stored cleaned up
+        for address in request.addresses.iter_mut().flatten() {
+            address.normalize();
+        }
         self.validate_create_business_rules(&request).await?;
+        if let Some(plan_limits) = &self.plan_limits {
+            plan_limits.ensure_room(self.tenant_context.tenant_id.0, PlanResource::Customers).await?;
+        }
 
         // Clone request early to avoid partial move issues
         let mut request_with_number = request.clone();
@@ -88,7 +127,9 @@ impl CustomerService for DefaultCustomerService {
         };
 
         // 4. Validate customer number uniqueness
-        if !self.repository.is_customer_number_available(&customer_number).await? {
+        if !self.repository.is_customer_number_available(&customer_number)
+            .instrument(spans::repository("is_customer_number_available"))
+            .await? {
             return Err(MasterDataError::DuplicateCustomerNumber {
                 number: customer_number,
             });
@@ -99,7 +140,9 @@ impl CustomerService for DefaultCustomerService {
 
         // 6. Set customer number
         request_with_number.customer_number = Some(customer_number);
-        let customer = self.repository.create_customer(&request_with_number, created_by).await?;
+        let customer = self.repository.create_customer(&request_with_number, created_by)
+            .instrument(spans::repository("create_customer"))
+            .await?;
 
         // 7. Post-creation business logic
         self.handle_post_creation_logic(&customer).await?;
@@ -107,7 +150,77 @@ impl CustomerService for DefaultCustomerService {
         Ok(customer)
     }
 
+    async fn bulk_create_customers(
+        &self,
+        mut requests: Vec<CreateCustomerRequest>,
+        options: BulkImportOptions,
+        created_by: Uuid,
+    ) -> Result<BulkImportResult> {
+        let _latency = self.latency.start("customer.write", "bulk_create_customers");
+        if !self.tenant_context.has_permission("customer:create") {
+            return Err(MasterDataError::ValidationError {
+                field: "permissions".to_string(),
+                message: "Insufficient permissions to create customer".to_string(),
+            });
+        }
+
+        // A row that fails the checks of a single create is reported, not inserted
+        for address in requests.iter_mut().flat_map(|r| r.addresses.iter_mut().flatten()) {
+            address.normalize();
+        }
+        let validator = CustomerValidator::new();
+        let mut rows = Vec::new();
+        let mut accepted = Vec::new();
+        for (index, request) in requests.into_iter().enumerate() {
+            match self.validate_import_row(&validator, &request).await {
+                Ok(()) => accepted.push((index, request)),
+                Err(error) => rows.push(BulkImportRow::Failed { index, error }),
+            }
+        }
+
+        // Rows past the room left in the plan
+        if let Some(plan_limits) = &self.plan_limits {
+            if let Some(room) = plan_limits.room(self.tenant_context.tenant_id.0, PlanResource::Customers).await? {
+                let room = usize::try_from(room).unwrap_or(0);
+                if accepted.len() > room {
+                    rows.extend(accepted.split_off(room).into_iter().map(|(index, _)| BulkImportRow::Failed {
+                        index,
+                        error: BulkImportError::PlanLimit,
+                    }));
+                }
+            }
+        }
+
+        let result = if options.atomic && !rows.is_empty() {
+            rows.extend(accepted.into_iter().map(|(index, _)| BulkImportRow::Failed {
+                index,
+                error: BulkImportError::Aborted,
+            }));
+            BulkImportResult::aborted(rows)
+        } else {
+            let (indices, requests): (Vec<usize>, Vec<CreateCustomerRequest>) = accepted.into_iter().unzip();
+            let imported = self.repository.bulk_create_customers(requests, &options, created_by)
+                .instrument(spans::repository("bulk_create_customers"))
+                .await?;
+            rows.extend(imported.rows.into_iter().map(|row| row.renumbered(&indices)));
+            if imported.aborted {
+                BulkImportResult::aborted(rows)
+            } else {
+                BulkImportResult::new(rows)
+            }
+        };
+        tracing::info!(
+            "Bulk customer import: {} created, {} failed{}",
+            result.created(),
+            result.failed(),
+            if result.aborted { ", rolled back" } else { "" }
+        );
+        Ok(result)
+    }
+
+    #[tracing::instrument(skip_all, fields(tenant_id = %self.tenant_context.tenant_id, customer_id = %id))]
     async fn update_customer(&self, id: Uuid, request: UpdateCustomerRequest, modified_by: Uuid) -> Result<Customer> {
+        let _latency = self.latency.start("customer.write", "update_customer");
         // 1. Input validation
         request.validate()
             .map_err(|e| MasterDataError::ValidationError {
@@ -116,7 +229,7 @@ impl CustomerService for DefaultCustomerService {
             })?;
 
         // 2. Get existing customer
-        let existing = self.repository.get_customer_by_id(id).await?
+        let existing = self.repository.get_customer_by_id(id).instrument(spans::repository("get_customer_by_id")).await?
             .ok_or(MasterDataError::CustomerNotFound { id: id.to_string() })?;
 
         // 3. Business rule validation for updates
@@ -131,7 +244,9 @@ impl CustomerService for DefaultCustomerService {
         // 5. Validate customer number changes
         if let Some(ref new_number) = request.customer_number {
             if new_number != &existing.customer_number {
-                if !self.repository.is_customer_number_available(new_number).await? {
+                if !self.repository.is_customer_number_available(new_number)
+                    .instrument(spans::repository("is_customer_number_available"))
+                    .await? {
                     return Err(MasterDataError::DuplicateCustomerNumber {
                         number: new_number.clone(),
                     });
@@ -140,7 +255,9 @@ impl CustomerService for DefaultCustomerService {
         }
 
         // 6. Update customer
-        let updated_customer = self.repository.update_customer(id, &request, modified_by).await?;
+        let updated_customer = self.repository.update_customer(id, &request, modified_by)
+            .instrument(spans::repository("update_customer"))
+            .await?;
 
         // 7. Post-update business logic
         self.handle_post_update_logic(&existing, &updated_customer).await?;
@@ -148,29 +265,38 @@ impl CustomerService for DefaultCustomerService {
         Ok(updated_customer)
     }
 
+    #[tracing::instrument(skip_all, fields(tenant_id = %self.tenant_context.tenant_id, customer_id = %id))]
     async fn get_customer(&self, id: Uuid) -> Result<Option<Customer>> {
-        self.repository.get_customer_by_id(id).await
+        let _latency = self.latency.start("customer.read", "get_customer");
+        self.repository.get_customer_by_id(id).instrument(spans::repository("get_customer_by_id")).await
     }
 
+    #[tracing::instrument(skip_all, fields(tenant_id = %self.tenant_context.tenant_id))]
     async fn search_customers(&self, criteria: CustomerSearchCriteria) -> Result<CustomerSearchResponse> {
+        let _latency = self.latency.start("customer.search", "search_customers");
         // Apply business rule filters
         let filtered_criteria = self.apply_business_rule_filters(criteria).await?;
 
-        let customers = self.repository.search_customers(&filtered_criteria).await?;
+        let page = self.repository.search_customers_page(&filtered_criteria)
+            .instrument(spans::repository("search_customers_page"))
+            .await?;
 
         // Convert to CustomerSearchResponse with basic pagination info
         Ok(CustomerSearchResponse {
-            customers,
+            customers: page.customers,
             total_count: 0, // Would need separate count query
-            page: 1,
-            page_size: 50,
+            page: filtered_criteria.page.unwrap_or(1),
+            page_size: filtered_criteria.page_size.unwrap_or(50),
             total_pages: 1,
+            next_cursor: page.next_cursor,
         })
     }
 
+    #[tracing::instrument(skip_all, fields(tenant_id = %self.tenant_context.tenant_id, customer_id = %id))]
     async fn delete_customer(&self, id: Uuid, deleted_by: Uuid) -> Result<()> {
+        let _latency = self.latency.start("customer.write", "delete_customer");
         // 1. Get existing customer
-        let customer = self.repository.get_customer_by_id(id).await?
+        let customer = self.repository.get_customer_by_id(id).instrument(spans::repository("get_customer_by_id")).await?
             .ok_or(MasterDataError::CustomerNotFound { id: id.to_string() })?;
 
         // 2. Validate deletion constraints
@@ -182,11 +308,15 @@ impl CustomerService for DefaultCustomerService {
         }
 
         // 4. Soft delete
-        self.repository.delete_customer(id, deleted_by).await
+        self.repository.delete_customer(id, deleted_by).instrument(spans::repository("delete_customer")).await
     }
 
+    #[tracing::instrument(skip_all, fields(tenant_id = %self.tenant_context.tenant_id, customer_id = %customer_id))]
     async fn validate_credit_limit_increase(&self, customer_id: Uuid, new_limit: rust_decimal::Decimal) -> Result<()> {
-        let customer = self.repository.get_customer_by_id(customer_id).await?
+        let _latency = self.latency.start("customer.read", "validate_credit_limit_increase");
+        let customer = self.repository.get_customer_by_id(customer_id)
+            .instrument(spans::repository("get_customer_by_id"))
+            .await?
             .ok_or(MasterDataError::CustomerNotFound { id: customer_id.to_string() })?;
 
         // Business rules for credit limit increases
@@ -219,8 +349,12 @@ impl CustomerService for DefaultCustomerService {
         Ok(())
     }
 
+    #[tracing::instrument(skip_all, fields(tenant_id = %self.tenant_context.tenant_id, customer_id = %customer_id))]
     async fn update_lifecycle_stage(&self, customer_id: Uuid, new_stage: CustomerLifecycleStage, updated_by: Uuid) -> Result<()> {
-        let customer = self.repository.get_customer_by_id(customer_id).await?
+        let _latency = self.latency.start("customer.write", "update_lifecycle_stage");
+        let customer = self.repository.get_customer_by_id(customer_id)
+            .instrument(spans::repository("get_customer_by_id"))
+            .await?
             .ok_or(MasterDataError::CustomerNotFound { id: customer_id.to_string() })?;
 
         // Validate lifecycle stage transitions
@@ -233,12 +367,16 @@ impl CustomerService for DefaultCustomerService {
             ..Default::default()
         };
 
-        self.repository.update_customer(customer_id, &update_request, updated_by).await?;
+        self.repository.update_customer(customer_id, &update_request, updated_by)
+            .instrument(spans::repository("update_customer"))
+            .await?;
 
         Ok(())
     }
 
+    #[tracing::instrument(skip_all, fields(tenant_id = %self.tenant_context.tenant_id, customer_id = %_customer_id))]
     async fn calculate_performance_metrics(&self, _customer_id: Uuid) -> Result<CustomerPerformanceMetrics> {
+        let _latency = self.latency.start("customer.analytics", "calculate_performance_metrics");
         // This would typically integrate with order management, payment systems, etc.
         // For now, return basic metrics structure
         Ok(CustomerPerformanceMetrics {
@@ -266,7 +404,9 @@ impl CustomerService for DefaultCustomerService {
         })
     }
 
+    #[tracing::instrument(skip_all, fields(tenant_id = %self.tenant_context.tenant_id))]
     async fn generate_customer_number(&self, customer_type: CustomerType) -> Result<String> {
+        let _latency = self.latency.start("customer.write", "generate_customer_number");
         // Business rules for customer number generation
         let prefix = match customer_type {
             CustomerType::B2b => "B2B",
@@ -288,10 +428,18 @@ impl CustomerService for DefaultCustomerService {
         Ok(format!("{}{:06}", prefix, sequence))
     }
 
+    #[tracing::instrument(skip_all, fields(
+        tenant_id = %self.tenant_context.tenant_id,
+        customer_id = customer_id.map(tracing::field::display),
+        parent_id = parent_id.map(tracing::field::display)
+    ))]
     async fn validate_hierarchy(&self, customer_id: Option<Uuid>, parent_id: Option<Uuid>) -> Result<()> {
+        let _latency = self.latency.start("customer.read", "validate_hierarchy");
         if let Some(parent_id) = parent_id {
             // Validate parent exists
-            let _parent = self.repository.get_customer_by_id(parent_id).await?
+            let _parent = self.repository.get_customer_by_id(parent_id)
+                .instrument(spans::repository("get_customer_by_id"))
+                .await?
                 .ok_or(MasterDataError::CustomerNotFound { id: parent_id.to_string() })?;
 
             // Prevent circular hierarchy
@@ -341,9 +489,37 @@ impl DefaultCustomerService {
             }
         }
 
+        // Rule: addresses must follow their country's postal rules
+        let validator = CustomerValidator::new();
+        for (i, address) in request.addresses.iter().flatten().enumerate() {
+            validator.validate_address(&format!("addresses[{}]", i), &address.postal_address())?;
+        }
+
+        // Tax numbers that cannot be checked are kept, with a warning
+        if let Some(ref tax_numbers) = request.tax_numbers {
+            warn_unverifiable_tax_numbers(tax_numbers);
+        }
+
         Ok(())
     }
 
+    /// The checks of a single create for one row of a bulk import
+    async fn validate_import_row(&self, validator: &CustomerValidator, request: &CreateCustomerRequest) -> std::result::Result<(), BulkImportError> {
+        validate_row(validator, request)?;
+        self.validate_create_business_rules(request)
+            .await
+            .map_err(|e| BulkImportError::from_error(e, None))?;
+        self.validate_hierarchy(None, request.parent_customer_id)
+            .await
+            .map_err(|e| match e {
+                MasterDataError::CustomerNotFound { id } => BulkImportError::Validation {
+                    field: "parent_customer_id".to_string(),
+                    message: format!("Parent customer {} not found", id),
+                },
+                other => BulkImportError::from_error(other, None),
+            })
+    }
+
     async fn validate_update_business_rules(&self, existing: &Customer, request: &UpdateCustomerRequest) -> Result<()> {
         // Rule: Cannot change customer type if customer has orders
         if let Some(new_type) = &request.customer_type {
@@ -407,7 +583,9 @@ impl DefaultCustomerService {
         }
 
         // Rule: Cannot delete customers with children
-        let children = self.repository.get_customer_hierarchy(customer.id).await?;
+        let children = self.repository.get_customer_hierarchy(customer.id)
+            .instrument(spans::repository("get_customer_hierarchy"))
+            .await?;
         if children.len() > 1 { // More than just the customer itself
             return Err(MasterDataError::ValidationError {
                 field: "hierarchy".to_string(),
@@ -448,12 +626,284 @@ impl DefaultCustomerService {
 
     async fn would_create_circular_hierarchy(&self, customer_id: Uuid, parent_id: Uuid) -> Result<bool> {
         // Check if setting parent_id as parent of customer_id would create a cycle
-        let hierarchy = self.repository.get_customer_hierarchy(parent_id).await?;
+        let hierarchy = self.repository.get_customer_hierarchy(parent_id)
+            .instrument(spans::repository("get_customer_hierarchy"))
+            .await?;
         Ok(hierarchy.iter().any(|c| c.id == customer_id))
     }
 
     async fn calculate_hierarchy_level(&self, parent_id: Uuid) -> Result<u8> {
-        let hierarchy = self.repository.get_customer_hierarchy(parent_id).await?;
+        let hierarchy = self.repository.get_customer_hierarchy(parent_id)
+            .instrument(spans::repository("get_customer_hierarchy"))
+            .await?;
         Ok(hierarchy.len() as u8)
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::customer::cursor::CustomerPage;
+    use crate::customer::field_encryption::MaskedFinancialIdentifiers;
+    use crate::types::{Address, ContactInfo};
+    use erp_core::{LatencyBudgetConfig, TenantId};
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+    use std::time::Duration;
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id};
+    use tracing::{Event, Subscriber};
+    use tracing_subscriber::layer::{Context, SubscriberExt};
+    use tracing_subscriber::registry::LookupSpan;
+    use tracing_subscriber::Layer;
+
+    /// A span or event: its name (the message of an event), the span it is
+    /// in and its fields
+    #[derive(Debug, Clone)]
+    struct Recorded {
+        name: String,
+        parent: Option<String>,
+        fields: HashMap<String, String>,
+    }
+
+    #[derive(Clone, Default)]
+    struct Collector {
+        spans: Arc<Mutex<Vec<Recorded>>>,
+        events: Arc<Mutex<Vec<Recorded>>>,
+    }
+
+    struct Fields(HashMap<String, String>);
+
+    impl Visit for Fields {
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            self.0.insert(field.name().to_string(), format!("{:?}", value));
+        }
+
+        fn record_str(&mut self, field: &Field, value: &str) {
+            self.0.insert(field.name().to_string(), value.to_string());
+        }
+    }
+
+    impl<S> Layer<S> for Collector
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+            let mut fields = Fields(HashMap::new());
+            attrs.record(&mut fields);
+            let parent = ctx.span(id).and_then(|span| span.parent()).map(|parent| parent.name().to_string());
+            self.spans.lock().unwrap().push(Recorded {
+                name: attrs.metadata().name().to_string(),
+                parent,
+                fields: fields.0,
+            });
+        }
+
+        fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+            let mut fields = Fields(HashMap::new());
+            event.record(&mut fields);
+            self.events.lock().unwrap().push(Recorded {
+                name: fields.0.remove("message").unwrap_or_default(),
+                parent: ctx.event_span(event).map(|span| span.name().to_string()),
+                fields: fields.0,
+            });
+        }
+    }
+
+    /// Searches take longer than the budget of `customer.search`
+    struct SlowSearchRepository;
+
+    #[async_trait]
+    impl CustomerRepository for SlowSearchRepository {
+        async fn create_customer(&self, _request: &CreateCustomerRequest, _created_by: Uuid) -> Result<Customer> { unimplemented!() }
+        async fn bulk_create_customers(&self, _requests: Vec<CreateCustomerRequest>, _options: &BulkImportOptions, _created_by: Uuid) -> Result<BulkImportResult> { unimplemented!() }
+        async fn get_customer_by_id(&self, _id: Uuid) -> Result<Option<Customer>> { Ok(None) }
+        async fn get_customer_by_number(&self, _customer_number: &str) -> Result<Option<Customer>> { unimplemented!() }
+        async fn update_customer(&self, _id: Uuid, _update: &UpdateCustomerRequest, _modified_by: Uuid) -> Result<Customer> { unimplemented!() }
+        async fn delete_customer(&self, _id: Uuid, _deleted_by: Uuid) -> Result<()> { unimplemented!() }
+        async fn list_customers(&self, _criteria: &CustomerSearchCriteria, _page: u32, _page_size: u32) -> Result<CustomerSearchResponse> { unimplemented!() }
+        async fn get_customer_hierarchy(&self, _customer_id: Uuid) -> Result<Vec<Customer>> { unimplemented!() }
+        async fn get_customers_by_corporate_group(&self, _group_id: Uuid) -> Result<Vec<Customer>> { unimplemented!() }
+        async fn get_customer_addresses(&self, _customer_id: Uuid) -> Result<Vec<Address>> { unimplemented!() }
+        async fn get_customer_contacts(&self, _customer_id: Uuid) -> Result<Vec<ContactInfo>> { unimplemented!() }
+        async fn search_customers(&self, _criteria: &CustomerSearchCriteria) -> Result<Vec<Customer>> { unimplemented!() }
+        async fn search_customers_page(&self, _criteria: &CustomerSearchCriteria) -> Result<CustomerPage> {
+            tokio::time::sleep(Duration::from_millis(30)).await;
+            Ok(CustomerPage::default())
+        }
+        async fn is_customer_number_available(&self, _customer_number: &str) -> Result<bool> { unimplemented!() }
+        async fn find_customers_by_tax_number(&self, _tax_number: &str) -> Result<Vec<Customer>> { unimplemented!() }
+        async fn find_customers_by_iban(&self, _iban: &str) -> Result<Vec<Customer>> { unimplemented!() }
+        async fn get_masked_financial_identifiers(&self, _customer_id: Uuid) -> Result<Option<MaskedFinancialIdentifiers>> { unimplemented!() }
+    }
+
+    #[tokio::test]
+    async fn test_slow_repository_call_is_traced_and_exceeds_the_budget() {
+        let collector = Collector::default();
+        let _subscriber = tracing::subscriber::set_default(tracing_subscriber::registry().with(collector.clone()));
+        let tenant_id = Uuid::new_v4();
+        let service = DefaultCustomerService::new(
+            Box::new(SlowSearchRepository),
+            TenantContext { tenant_id: TenantId(tenant_id), schema_name: "tenant".to_string() },
+        )
+        .with_latency_budget(LatencyBudget::new(LatencyBudgetConfig {
+            default_ms: 1000,
+            groups: HashMap::from([("customer.search".to_string(), 10)]),
+        }));
+
+        service.search_customers(CustomerSearchCriteria::default()).await.unwrap();
+        let customer_id = Uuid::new_v4();
+        service.get_customer(customer_id).await.unwrap();
+
+        let spans = collector.spans.lock().unwrap().clone();
+        let names: Vec<(&str, Option<&str>)> = spans.iter().map(|span| (span.name.as_str(), span.parent.as_deref())).collect();
+        assert_eq!(
+            names,
+            vec![
+                ("search_customers", None),
+                ("repository", Some("search_customers")),
+                ("get_customer", None),
+                ("repository", Some("get_customer")),
+            ]
+        );
+        assert_eq!(spans[0].fields["tenant_id"], tenant_id.to_string());
+        assert_eq!(spans[1].fields["operation"], "search_customers_page");
+        assert_eq!(spans[2].fields["customer_id"], customer_id.to_string());
+
+        // Only the search took longer than its budget; the event is in its span
+        let events = collector.events.lock().unwrap().clone();
+        assert_eq!(events.len(), 1, "{:?}", events);
+        assert_eq!(events[0].name, "latency budget exceeded");
+        assert_eq!(events[0].parent.as_deref(), Some("search_customers"));
+        assert_eq!(events[0].fields["group"], "customer.search");
+        assert_eq!(events[0].fields["budget_ms"], "10");
+    }
+
+    /// Stores every row it is given, numbering those without a customer number
+    struct ImportRepository {
+        imported: Arc<Mutex<Vec<String>>>,
+    }
+
+    #[async_trait]
+    impl CustomerRepository for ImportRepository {
+        async fn create_customer(&self, _request: &CreateCustomerRequest, _created_by: Uuid) -> Result<Customer> { unimplemented!() }
+        async fn bulk_create_customers(&self, requests: Vec<CreateCustomerRequest>, _options: &BulkImportOptions, _created_by: Uuid) -> Result<BulkImportResult> {
+            let mut imported = self.imported.lock().unwrap();
+            let rows = requests
+                .into_iter()
+                .enumerate()
+                .map(|(index, request)| {
+                    let customer_number = request.customer_number.unwrap_or_else(|| format!("GEN-{}", index));
+                    imported.push(customer_number.clone());
+                    BulkImportRow::Created { index, customer_id: Uuid::new_v4(), customer_number }
+                })
+                .collect();
+            Ok(BulkImportResult::new(rows))
+        }
+        async fn get_customer_by_id(&self, _id: Uuid) -> Result<Option<Customer>> { Ok(None) }
+        async fn get_customer_by_number(&self, _customer_number: &str) -> Result<Option<Customer>> { unimplemented!() }
+        async fn update_customer(&self, _id: Uuid, _update: &UpdateCustomerRequest, _modified_by: Uuid) -> Result<Customer> { unimplemented!() }
+        async fn delete_customer(&self, _id: Uuid, _deleted_by: Uuid) -> Result<()> { unimplemented!() }
+        async fn list_customers(&self, _criteria: &CustomerSearchCriteria, _page: u32, _page_size: u32) -> Result<CustomerSearchResponse> { unimplemented!() }
+        async fn get_customer_hierarchy(&self, _customer_id: Uuid) -> Result<Vec<Customer>> { unimplemented!() }
+        async fn get_customers_by_corporate_group(&self, _group_id: Uuid) -> Result<Vec<Customer>> { unimplemented!() }
+        async fn get_customer_addresses(&self, _customer_id: Uuid) -> Result<Vec<Address>> { unimplemented!() }
+        async fn get_customer_contacts(&self, _customer_id: Uuid) -> Result<Vec<ContactInfo>> { unimplemented!() }
+        async fn search_customers(&self, _criteria: &CustomerSearchCriteria) -> Result<Vec<Customer>> { unimplemented!() }
+        async fn search_customers_page(&self, _criteria: &CustomerSearchCriteria) -> Result<CustomerPage> { unimplemented!() }
+        async fn is_customer_number_available(&self, _customer_number: &str) -> Result<bool> { unimplemented!() }
+        async fn find_customers_by_tax_number(&self, _tax_number: &str) -> Result<Vec<Customer>> { unimplemented!() }
+        async fn find_customers_by_iban(&self, _iban: &str) -> Result<Vec<Customer>> { unimplemented!() }
+        async fn get_masked_financial_identifiers(&self, _customer_id: Uuid) -> Result<Option<MaskedFinancialIdentifiers>> { unimplemented!() }
+    }
+
+    /// A plan with room for a fixed number of customers
+    struct FixedRoom(i64);
+
+    #[async_trait]
+    impl PlanLimitGuard for FixedRoom {
+        async fn ensure_room(&self, _tenant_id: Uuid, _resource: PlanResource) -> erp_core::Result<()> {
+            Ok(())
+        }
+
+        async fn room(&self, _tenant_id: Uuid, _resource: PlanResource) -> erp_core::Result<Option<i64>> {
+            Ok(Some(self.0))
+        }
+    }
+
+    fn import_request(value: serde_json::Value) -> CreateCustomerRequest {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn import_requests() -> Vec<CreateCustomerRequest> {
+        vec![
+            import_request(serde_json::json!({ "customer_number": "C-0", "legal_name": "Acme GmbH", "customer_type": CustomerType::B2b })),
+            import_request(serde_json::json!({ "legal_name": "A", "customer_type": CustomerType::B2b })),
+            import_request(serde_json::json!({
+                "legal_name": "Internal Sales",
+                "customer_type": CustomerType::Internal,
+                "external_ids": { "crm": "42" }
+            })),
+            import_request(serde_json::json!({
+                "legal_name": "Orphan GmbH",
+                "customer_type": CustomerType::B2b,
+                "parent_customer_id": Uuid::new_v4()
+            })),
+            import_request(serde_json::json!({ "customer_number": "C-4", "legal_name": "Beta AG", "customer_type": CustomerType::B2b })),
+            import_request(serde_json::json!({ "legal_name": "Gamma SE", "customer_type": CustomerType::B2b })),
+        ]
+    }
+
+    fn failed_field(row: &BulkImportRow) -> Option<&str> {
+        match row {
+            BulkImportRow::Failed { error: BulkImportError::Validation { field, .. }, .. } => Some(field),
+            _ => None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_bulk_import_applies_business_rules_and_plan_room_per_row() {
+        let imported = Arc::new(Mutex::new(Vec::new()));
+        let service = DefaultCustomerService::new(
+            Box::new(ImportRepository { imported: imported.clone() }),
+            TenantContext { tenant_id: TenantId(Uuid::new_v4()), schema_name: "tenant".to_string() },
+        )
+        .with_plan_limits(Arc::new(FixedRoom(1)));
+
+        let result = service
+            .bulk_create_customers(import_requests(), BulkImportOptions::default(), Uuid::new_v4())
+            .await
+            .unwrap();
+
+        assert_eq!(*imported.lock().unwrap(), vec!["C-0".to_string()]);
+        assert_eq!(result.rows.iter().map(BulkImportRow::index).collect::<Vec<_>>(), vec![0, 1, 2, 3, 4, 5]);
+        assert!(matches!(&result.rows[0], BulkImportRow::Created { customer_number, .. } if customer_number == "C-0"));
+        assert_eq!(failed_field(&result.rows[1]), Some("legal_name"));
+        assert_eq!(failed_field(&result.rows[2]), Some("external_ids"));
+        assert_eq!(failed_field(&result.rows[3]), Some("parent_customer_id"));
+        for row in &result.rows[4..] {
+            assert!(matches!(row, BulkImportRow::Failed { error: BulkImportError::PlanLimit, .. }), "{:?}", row);
+        }
+        assert!(!result.aborted);
+    }
+
+    #[tokio::test]
+    async fn test_atomic_bulk_import_with_a_refused_row_stores_nothing() {
+        let imported = Arc::new(Mutex::new(Vec::new()));
+        let service = DefaultCustomerService::new(
+            Box::new(ImportRepository { imported: imported.clone() }),
+            TenantContext { tenant_id: TenantId(Uuid::new_v4()), schema_name: "tenant".to_string() },
+        )
+        .with_plan_limits(Arc::new(FixedRoom(10)));
+
+        let result = service
+            .bulk_create_customers(import_requests(), BulkImportOptions { atomic: true, ..BulkImportOptions::default() }, Uuid::new_v4())
+            .await
+            .unwrap();
+
+        assert!(imported.lock().unwrap().is_empty());
+        assert!(result.aborted);
+        assert_eq!(result.created(), 0);
+        assert!(matches!(result.rows[0], BulkImportRow::Failed { error: BulkImportError::Aborted, .. }));
+        assert_eq!(failed_field(&result.rows[2]), Some("external_ids"));
+    }
+}
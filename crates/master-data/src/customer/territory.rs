@@ -0,0 +1,612 @@
+//! # Sales Territories
+//!
+//! A territory names the sales representative and account manager responsible
+//! for the customers its rules match. Rules list accepted values per
+//! criterion: country, region, postal code prefix, industry and business
+//! size. A customer matches when it satisfies every criterion that lists
+//! values; an empty list accepts anything. Where active territories overlap,
+//! the one with the highest `priority` wins, ties going to the older one.
+//!
+//! ## Assignment
+//!
+//! Each role (sales representative, account manager) of a customer has an
+//! assignee derived from its winning territory and an optional explicit
+//! override set on the customer. The override wins; the derived assignee is
+//! kept next to it, so removing the override falls back to the territory.
+//! The effective assignee stays in `sales_representative_id` and
+//! `account_manager_id` of the customer.
+//!
+//! ## Re-assignment
+//!
+//! Derived assignees are stored on the customer and re-evaluated by a
+//! re-assignment run once territories change. [`plan_reassignment`] computes
+//! what the run would change; the preview returns that plan and applying it
+//! books the very same plan. Every change of an effective assignee is
+//! recorded as an [`OwnershipChange`], which CRM syncs read through the
+//! change log (entity type `customer_ownership`).
+
+use crate::error::{MasterDataError, Result};
+use crate::types::{BusinessSize, IndustryClassification};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+/// Create, change and delete territories and run re-assignments
+pub const PERMISSION_MANAGE_TERRITORIES: &str = "territories:manage";
+
+const MAX_NAME_LENGTH: usize = 100;
+
+/// Values a territory accepts per criterion; an empty list accepts anything
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TerritoryRules {
+    /// ISO country codes of the customer's primary address
+    pub countries: Vec<String>,
+    /// State or province of the primary address
+    pub regions: Vec<String>,
+    /// Prefixes of the primary address' postal code, compared without spaces
+    pub postal_prefixes: Vec<String>,
+    pub industries: Vec<IndustryClassification>,
+    pub sizes: Vec<BusinessSize>,
+}
+
+fn normalize_postal(value: &str) -> String {
+    value.chars().filter(|c| !c.is_whitespace()).collect::<String>().to_uppercase()
+}
+
+fn accepts<T>(values: &[T], value: Option<&T>, eq: impl Fn(&T, &T) -> bool) -> bool {
+    values.is_empty() || value.is_some_and(|value| values.iter().any(|accepted| eq(accepted, value)))
+}
+
+impl TerritoryRules {
+    pub fn matches(&self, profile: &CustomerProfile) -> bool {
+        let same_text = |a: &String, b: &String| a.trim().eq_ignore_ascii_case(b.trim());
+        let postal = profile.postal_code.as_deref().map(normalize_postal);
+
+        accepts(&self.countries, profile.country_code.as_ref(), same_text)
+            && accepts(&self.regions, profile.region.as_ref(), same_text)
+            && accepts(&self.postal_prefixes, postal.as_ref(), |prefix, code| {
+                code.starts_with(&normalize_postal(prefix))
+            })
+            && accepts(&self.industries, profile.industry.as_ref(), PartialEq::eq)
+            && accepts(&self.sizes, profile.size.as_ref(), PartialEq::eq)
+    }
+
+    fn validate(&self) -> Result<()> {
+        let blank = |values: &[String]| values.iter().any(|value| value.trim().is_empty());
+        if blank(&self.countries) || blank(&self.regions) || blank(&self.postal_prefixes) {
+            return Err(MasterDataError::ValidationError {
+                field: "rules".to_string(),
+                message: "Territory rules must not contain empty values".to_string(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// A sales territory
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Territory {
+    pub id: Uuid,
+    pub tenant_id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    /// Decides between overlapping territories; the highest wins
+    pub priority: i32,
+    pub rules: TerritoryRules,
+    pub sales_representative_id: Option<Uuid>,
+    pub account_manager_id: Option<Uuid>,
+    /// Inactive territories match nobody
+    pub is_active: bool,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Definition of a territory, for creating one and for replacing it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TerritoryRequest {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub priority: i32,
+    #[serde(default)]
+    pub rules: TerritoryRules,
+    #[serde(default)]
+    pub sales_representative_id: Option<Uuid>,
+    #[serde(default)]
+    pub account_manager_id: Option<Uuid>,
+    #[serde(default = "default_active")]
+    pub is_active: bool,
+}
+
+fn default_active() -> bool {
+    true
+}
+
+impl Territory {
+    pub fn create(tenant_id: Uuid, request: TerritoryRequest, created_by: Uuid, now: DateTime<Utc>) -> Result<Self> {
+        let mut territory = Self {
+            id: Uuid::new_v4(),
+            tenant_id,
+            name: String::new(),
+            description: None,
+            priority: 0,
+            rules: TerritoryRules::default(),
+            sales_representative_id: None,
+            account_manager_id: None,
+            is_active: true,
+            created_by: Some(created_by),
+            created_at: now,
+            updated_at: now,
+        };
+        territory.apply(request, now)?;
+        Ok(territory)
+    }
+
+    /// Replace the definition with `request`
+    pub fn apply(&mut self, request: TerritoryRequest, now: DateTime<Utc>) -> Result<()> {
+        let name = request.name.trim();
+        if name.is_empty() || name.chars().count() > MAX_NAME_LENGTH {
+            return Err(MasterDataError::ValidationError {
+                field: "name".to_string(),
+                message: format!("Name is required and at most {} characters", MAX_NAME_LENGTH),
+            });
+        }
+        request.rules.validate()?;
+
+        self.name = name.to_string();
+        self.description = request.description;
+        self.priority = request.priority;
+        self.rules = request.rules;
+        self.sales_representative_id = request.sales_representative_id;
+        self.account_manager_id = request.account_manager_id;
+        self.is_active = request.is_active;
+        self.updated_at = now;
+        Ok(())
+    }
+
+    pub fn matches(&self, profile: &CustomerProfile) -> bool {
+        self.is_active && self.rules.matches(profile)
+    }
+}
+
+/// What the territory rules look at
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CustomerProfile {
+    pub customer_id: Uuid,
+    pub country_code: Option<String>,
+    pub region: Option<String>,
+    pub postal_code: Option<String>,
+    pub industry: Option<IndustryClassification>,
+    pub size: Option<BusinessSize>,
+}
+
+/// The active territory responsible for a customer
+pub fn winning_territory<'a>(territories: &'a [Territory], profile: &CustomerProfile) -> Option<&'a Territory> {
+    territories
+        .iter()
+        .filter(|territory| territory.matches(profile))
+        .min_by(|a, b| {
+            b.priority
+                .cmp(&a.priority)
+                .then(a.created_at.cmp(&b.created_at))
+                .then(a.id.cmp(&b.id))
+        })
+}
+
+/// Responsibility a territory assigns
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AssignmentRole {
+    SalesRepresentative,
+    AccountManager,
+}
+
+impl AssignmentRole {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AssignmentRole::SalesRepresentative => "sales_representative",
+            AssignmentRole::AccountManager => "account_manager",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "sales_representative" => Some(AssignmentRole::SalesRepresentative),
+            "account_manager" => Some(AssignmentRole::AccountManager),
+            _ => None,
+        }
+    }
+}
+
+/// Where the effective assignee of a role comes from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AssignmentSource {
+    Override,
+    Territory,
+    #[default]
+    Unassigned,
+}
+
+impl AssignmentSource {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AssignmentSource::Override => "override",
+            AssignmentSource::Territory => "territory",
+            AssignmentSource::Unassigned => "unassigned",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "override" => Some(AssignmentSource::Override),
+            "territory" => Some(AssignmentSource::Territory),
+            "unassigned" => Some(AssignmentSource::Unassigned),
+            _ => None,
+        }
+    }
+}
+
+/// Derived and overridden assignee of one role
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Assignee {
+    /// Who is responsible: the override if set, the derived assignee otherwise
+    pub assignee_id: Option<Uuid>,
+    pub derived_id: Option<Uuid>,
+    pub override_id: Option<Uuid>,
+    pub source: AssignmentSource,
+}
+
+impl Assignee {
+    pub fn resolve(derived_id: Option<Uuid>, override_id: Option<Uuid>) -> Self {
+        let (assignee_id, source) = match (override_id, derived_id) {
+            (Some(id), _) => (Some(id), AssignmentSource::Override),
+            (None, Some(id)) => (Some(id), AssignmentSource::Territory),
+            (None, None) => (None, AssignmentSource::Unassigned),
+        };
+        Self {
+            assignee_id,
+            derived_id,
+            override_id,
+            source,
+        }
+    }
+}
+
+/// Who is responsible for a customer, and why
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CustomerAssignment {
+    /// Territory the derived assignees come from
+    pub territory_id: Option<Uuid>,
+    pub sales_representative: Assignee,
+    pub account_manager: Assignee,
+}
+
+impl CustomerAssignment {
+    /// Assignment of a customer with the given overrides
+    pub fn evaluate(
+        territories: &[Territory],
+        profile: &CustomerProfile,
+        sales_representative_override: Option<Uuid>,
+        account_manager_override: Option<Uuid>,
+    ) -> Self {
+        let territory = winning_territory(territories, profile);
+        Self {
+            territory_id: territory.map(|t| t.id),
+            sales_representative: Assignee::resolve(
+                territory.and_then(|t| t.sales_representative_id),
+                sales_representative_override,
+            ),
+            account_manager: Assignee::resolve(territory.and_then(|t| t.account_manager_id), account_manager_override),
+        }
+    }
+
+    /// The same derived assignees with other overrides
+    pub fn with_overrides(&self, sales_representative: Option<Uuid>, account_manager: Option<Uuid>) -> Self {
+        Self {
+            territory_id: self.territory_id,
+            sales_representative: Assignee::resolve(self.sales_representative.derived_id, sales_representative),
+            account_manager: Assignee::resolve(self.account_manager.derived_id, account_manager),
+        }
+    }
+
+    pub fn role(&self, role: AssignmentRole) -> &Assignee {
+        match role {
+            AssignmentRole::SalesRepresentative => &self.sales_representative,
+            AssignmentRole::AccountManager => &self.account_manager,
+        }
+    }
+
+    /// Roles whose effective assignee differs in `next`, with the previous and new assignee
+    pub fn ownership_moves(&self, next: &CustomerAssignment) -> Vec<(AssignmentRole, Option<Uuid>, Option<Uuid>)> {
+        [AssignmentRole::SalesRepresentative, AssignmentRole::AccountManager]
+            .into_iter()
+            .filter_map(|role| {
+                let (before, after) = (self.role(role).assignee_id, next.role(role).assignee_id);
+                (before != after).then_some((role, before, after))
+            })
+            .collect()
+    }
+}
+
+/// Explicit assignees of a customer; `None` clears the override of that role
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AssignmentOverrideRequest {
+    #[serde(default)]
+    pub sales_representative_id: Option<Uuid>,
+    #[serde(default)]
+    pub account_manager_id: Option<Uuid>,
+}
+
+/// A customer as stored: what the rules see and its current assignment
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CustomerOwnership {
+    pub profile: CustomerProfile,
+    pub assignment: CustomerAssignment,
+}
+
+/// A change of the effective assignee of one role, as CRM syncs receive it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OwnershipChange {
+    pub id: Uuid,
+    pub tenant_id: Uuid,
+    pub customer_id: Uuid,
+    pub role: AssignmentRole,
+    pub previous_assignee_id: Option<Uuid>,
+    pub new_assignee_id: Option<Uuid>,
+    /// Source of the new assignee
+    pub source: AssignmentSource,
+    pub territory_id: Option<Uuid>,
+    pub changed_by: Option<Uuid>,
+    pub changed_at: DateTime<Utc>,
+}
+
+/// Ownership changes of moving a customer from `before` to `after`
+pub fn ownership_changes(
+    tenant_id: Uuid,
+    customer_id: Uuid,
+    before: &CustomerAssignment,
+    after: &CustomerAssignment,
+    changed_by: Uuid,
+    now: DateTime<Utc>,
+) -> Vec<OwnershipChange> {
+    before
+        .ownership_moves(after)
+        .into_iter()
+        .map(|(role, previous, new)| OwnershipChange {
+            id: Uuid::new_v4(),
+            tenant_id,
+            customer_id,
+            role,
+            previous_assignee_id: previous,
+            new_assignee_id: new,
+            source: after.role(role).source,
+            territory_id: after.territory_id,
+            changed_by: Some(changed_by),
+            changed_at: now,
+        })
+        .collect()
+}
+
+/// New assignment of one customer in a re-assignment
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlannedAssignment {
+    pub customer_id: Uuid,
+    pub before: CustomerAssignment,
+    pub after: CustomerAssignment,
+}
+
+/// Customers of one role moving from one assignee to another
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AssigneeMove {
+    pub role: AssignmentRole,
+    pub from_assignee_id: Option<Uuid>,
+    pub to_assignee_id: Option<Uuid>,
+    pub customers: usize,
+}
+
+/// What a re-assignment changes
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReassignmentPlan {
+    pub customers_evaluated: usize,
+    /// Customers whose effective sales representative or account manager changes
+    pub customers_moving: usize,
+    /// Moves per role and pair of assignees
+    pub moves: Vec<AssigneeMove>,
+    /// Every customer whose stored assignment changes, including derived
+    /// assignees hidden behind an override
+    pub assignments: Vec<PlannedAssignment>,
+}
+
+/// Re-evaluate the territories for every customer, keeping their overrides
+pub fn plan_reassignment(territories: &[Territory], customers: &[CustomerOwnership]) -> ReassignmentPlan {
+    let mut moves: BTreeMap<(AssignmentRole, Option<Uuid>, Option<Uuid>), usize> = BTreeMap::new();
+    let mut plan = ReassignmentPlan {
+        customers_evaluated: customers.len(),
+        ..Default::default()
+    };
+
+    for customer in customers {
+        let before = customer.assignment;
+        let after = CustomerAssignment::evaluate(
+            territories,
+            &customer.profile,
+            before.sales_representative.override_id,
+            before.account_manager.override_id,
+        );
+        if after == before {
+            continue;
+        }
+
+        let moved = before.ownership_moves(&after);
+        if !moved.is_empty() {
+            plan.customers_moving += 1;
+        }
+        for (role, from, to) in moved {
+            *moves.entry((role, from, to)).or_default() += 1;
+        }
+        plan.assignments.push(PlannedAssignment {
+            customer_id: customer.profile.customer_id,
+            before,
+            after,
+        });
+    }
+
+    plan.moves = moves
+        .into_iter()
+        .map(|((role, from_assignee_id, to_assignee_id), customers)| AssigneeMove {
+            role,
+            from_assignee_id,
+            to_assignee_id,
+            customers,
+        })
+        .collect();
+    plan
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn territory(name: &str, priority: i32, rules: TerritoryRules, rep: Uuid) -> Territory {
+        let request = TerritoryRequest {
+            name: name.to_string(),
+            description: None,
+            priority,
+            rules,
+            sales_representative_id: Some(rep),
+            account_manager_id: None,
+            is_active: true,
+        };
+        Territory::create(Uuid::nil(), request, Uuid::nil(), Utc::now()).unwrap()
+    }
+
+    fn profile(country: &str, postal_code: &str, industry: IndustryClassification) -> CustomerProfile {
+        CustomerProfile {
+            customer_id: Uuid::new_v4(),
+            country_code: Some(country.to_string()),
+            region: None,
+            postal_code: Some(postal_code.to_string()),
+            industry: Some(industry),
+            size: Some(BusinessSize::Medium),
+        }
+    }
+
+    fn rules(countries: &[&str], postal_prefixes: &[&str]) -> TerritoryRules {
+        TerritoryRules {
+            countries: countries.iter().map(|c| c.to_string()).collect(),
+            postal_prefixes: postal_prefixes.iter().map(|p| p.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_highest_priority_wins_where_territories_overlap() {
+        let (germany_rep, munich_rep, health_rep) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let germany = territory("Germany", 0, rules(&["DE"], &[]), germany_rep);
+        let munich = territory("Munich", 10, rules(&["de"], &["80", "81"]), munich_rep);
+        let mut healthcare = territory("Healthcare", 5, TerritoryRules {
+            industries: vec![IndustryClassification::Healthcare],
+            ..Default::default()
+        }, health_rep);
+        healthcare.created_at = munich.created_at - Duration::days(1);
+        let territories = vec![germany.clone(), munich.clone(), healthcare.clone()];
+
+        let in_munich = profile("DE", "80 331", IndustryClassification::Healthcare);
+        assert_eq!(winning_territory(&territories, &in_munich).map(|t| t.id), Some(munich.id));
+        let in_berlin = profile("DE", "10115", IndustryClassification::Healthcare);
+        assert_eq!(winning_territory(&territories, &in_berlin).map(|t| t.id), Some(healthcare.id));
+        let retail_in_berlin = profile("DE", "10115", IndustryClassification::Retail);
+        assert_eq!(winning_territory(&territories, &retail_in_berlin).map(|t| t.id), Some(germany.id));
+        assert!(winning_territory(&territories, &profile("AT", "1010", IndustryClassification::Retail)).is_none());
+
+        // Equal priority goes to the older territory; inactive ones match nobody
+        let mut older = territory("Bavaria", 10, rules(&["DE"], &["8"]), Uuid::new_v4());
+        older.created_at = munich.created_at - Duration::days(30);
+        let mut territories = vec![munich.clone(), older.clone()];
+        assert_eq!(winning_territory(&territories, &in_munich).map(|t| t.id), Some(older.id));
+        territories[1].is_active = false;
+        assert_eq!(winning_territory(&territories, &in_munich).map(|t| t.id), Some(munich.id));
+    }
+
+    #[test]
+    fn test_override_wins_over_the_territory() {
+        let (territory_rep, override_rep) = (Uuid::new_v4(), Uuid::new_v4());
+        let territories = vec![territory("Germany", 0, rules(&["DE"], &[]), territory_rep)];
+        let customer = profile("DE", "10115", IndustryClassification::Retail);
+
+        let assignment = CustomerAssignment::evaluate(&territories, &customer, Some(override_rep), None);
+        assert_eq!(assignment.sales_representative.assignee_id, Some(override_rep));
+        assert_eq!(assignment.sales_representative.derived_id, Some(territory_rep));
+        assert_eq!(assignment.sales_representative.source, AssignmentSource::Override);
+        assert_eq!(assignment.account_manager.source, AssignmentSource::Unassigned);
+
+        // Removing the override falls back to the territory
+        let cleared = assignment.with_overrides(None, None);
+        assert_eq!(cleared.sales_representative.assignee_id, Some(territory_rep));
+        assert_eq!(cleared.sales_representative.source, AssignmentSource::Territory);
+        let changes = ownership_changes(Uuid::nil(), customer.customer_id, &assignment, &cleared, Uuid::nil(), Utc::now());
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].previous_assignee_id, Some(override_rep));
+        assert_eq!(changes[0].new_assignee_id, Some(territory_rep));
+
+        // Redrawn territories never move an overridden customer
+        let redrawn = vec![territory("Germany", 0, rules(&["DE"], &[]), Uuid::new_v4())];
+        let plan = plan_reassignment(&redrawn, &[CustomerOwnership { profile: customer, assignment }]);
+        assert_eq!(plan.customers_moving, 0);
+        assert_eq!(plan.assignments.len(), 1, "the new derived assignee is still stored");
+        assert_eq!(plan.assignments[0].after.sales_representative.assignee_id, Some(override_rep));
+    }
+
+    #[test]
+    fn test_plan_counts_moves_between_assignees() {
+        let (old_rep, new_rep) = (Uuid::new_v4(), Uuid::new_v4());
+        let before = vec![territory("North", 0, rules(&["DE"], &["2"]), old_rep)];
+        let after = vec![
+            territory("North", 0, rules(&["DE"], &["2"]), old_rep),
+            territory("Hamburg", 5, rules(&["DE"], &["20", "21", "22"]), new_rep),
+        ];
+
+        let customers: Vec<CustomerOwnership> = ["20095", "21073", "24103", "10115"]
+            .iter()
+            .map(|postal_code| {
+                let profile = profile("DE", postal_code, IndustryClassification::Retail);
+                let assignment = CustomerAssignment::evaluate(&before, &profile, None, None);
+                CustomerOwnership { profile, assignment }
+            })
+            .collect();
+
+        let plan = plan_reassignment(&after, &customers);
+        assert_eq!(plan.customers_evaluated, 4);
+        assert_eq!(plan.customers_moving, 2);
+        assert_eq!(plan.moves, vec![AssigneeMove {
+            role: AssignmentRole::SalesRepresentative,
+            from_assignee_id: Some(old_rep),
+            to_assignee_id: Some(new_rep),
+            customers: 2,
+        }]);
+        assert!(plan_reassignment(&before, &customers).assignments.is_empty());
+    }
+
+    #[test]
+    fn test_territory_definitions_are_validated() {
+        let request = |name: &str, rules: TerritoryRules| TerritoryRequest {
+            name: name.to_string(),
+            description: None,
+            priority: 0,
+            rules,
+            sales_representative_id: None,
+            account_manager_id: None,
+            is_active: true,
+        };
+        assert!(Territory::create(Uuid::nil(), request(" ", TerritoryRules::default()), Uuid::nil(), Utc::now()).is_err());
+        assert!(Territory::create(Uuid::nil(), request("North", rules(&["DE"], &[""])), Uuid::nil(), Utc::now()).is_err());
+        let created = Territory::create(Uuid::nil(), request(" North ", rules(&["DE"], &["2"])), Uuid::nil(), Utc::now()).unwrap();
+        assert_eq!(created.name, "North");
+    }
+}
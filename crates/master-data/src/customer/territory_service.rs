@@ -0,0 +1,387 @@
+use async_trait::async_trait;
+use chrono::Utc;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::customer::communication::{PERMISSION_READ, PERMISSION_WRITE};
+use crate::customer::repository::TerritoryRepository;
+use crate::customer::territory::*;
+use crate::error::{MasterDataError, Result};
+use crate::types::TenantContext;
+
+/// Times an override is retried when the customer's assignment changes underneath
+const OVERRIDE_ATTEMPTS: usize = 3;
+
+/// Sales territories and the customer assignments derived from them
+#[async_trait]
+pub trait TerritoryService: Send + Sync {
+    /// Territories by descending priority
+    async fn list_territories(&self) -> Result<Vec<Territory>>;
+
+    async fn get_territory(&self, id: Uuid) -> Result<Territory>;
+
+    async fn create_territory(&self, request: TerritoryRequest) -> Result<Territory>;
+
+    /// Replace a territory's definition; customers move with the next re-assignment
+    async fn update_territory(&self, id: Uuid, request: TerritoryRequest) -> Result<Territory>;
+
+    async fn delete_territory(&self, id: Uuid) -> Result<()>;
+
+    /// Customers the rules match, regardless of other territories
+    async fn preview_matches(&self, rules: &TerritoryRules) -> Result<Vec<Uuid>>;
+
+    /// What a re-assignment would change, without changing anything
+    async fn preview_reassignment(&self) -> Result<ReassignmentPlan>;
+
+    /// Re-evaluate the territories for every customer and store the result;
+    /// returns the plan that was applied
+    async fn reassign(&self) -> Result<ReassignmentPlan>;
+
+    async fn get_assignment(&self, customer_id: Uuid) -> Result<CustomerAssignment>;
+
+    /// Derive the assignees of one customer, e.g. right after it was created.
+    /// Only applies the territories, so it needs no permission of its own.
+    async fn assign_customer(&self, customer_id: Uuid) -> Result<CustomerAssignment>;
+
+    /// Set or clear the explicit assignees of a customer
+    async fn set_overrides(&self, customer_id: Uuid, request: AssignmentOverrideRequest) -> Result<CustomerAssignment>;
+}
+
+pub struct DefaultTerritoryService {
+    repository: Arc<dyn TerritoryRepository>,
+    tenant_context: TenantContext,
+}
+
+impl DefaultTerritoryService {
+    pub fn new(repository: Arc<dyn TerritoryRepository>, tenant_context: TenantContext) -> Self {
+        Self {
+            repository,
+            tenant_context,
+        }
+    }
+
+    fn require(&self, permissions: &[&str], action: &str) -> Result<()> {
+        let allowed = std::iter::once(PERMISSION_MANAGE_TERRITORIES)
+            .chain(permissions.iter().copied())
+            .any(|permission| self.tenant_context.has_permission(permission));
+        if !allowed {
+            return Err(MasterDataError::PermissionDenied { action: action.to_string() });
+        }
+        Ok(())
+    }
+
+    async fn ownership(&self, customer_id: Uuid) -> Result<CustomerOwnership> {
+        self.repository
+            .customer_ownership(self.tenant_context.tenant_id, customer_id)
+            .await?
+            .ok_or_else(|| MasterDataError::CustomerNotFound { id: customer_id.to_string() })
+    }
+
+    /// Store a plan together with the ownership changes it causes
+    async fn apply(&self, plan: &ReassignmentPlan) -> Result<usize> {
+        let now = Utc::now();
+        let changes: Vec<OwnershipChange> = plan
+            .assignments
+            .iter()
+            .flat_map(|planned| {
+                ownership_changes(
+                    self.tenant_context.tenant_id,
+                    planned.customer_id,
+                    &planned.before,
+                    &planned.after,
+                    self.tenant_context.user_id,
+                    now,
+                )
+            })
+            .collect();
+
+        self.repository
+            .store_assignments(self.tenant_context.tenant_id, &plan.assignments, &changes)
+            .await
+    }
+
+    async fn plan(&self) -> Result<ReassignmentPlan> {
+        let tenant_id = self.tenant_context.tenant_id;
+        let territories = self.repository.list_territories(tenant_id).await?;
+        let customers = self.repository.customer_ownerships(tenant_id).await?;
+        Ok(plan_reassignment(&territories, &customers))
+    }
+}
+
+#[async_trait]
+impl TerritoryService for DefaultTerritoryService {
+    async fn list_territories(&self) -> Result<Vec<Territory>> {
+        self.require(&[PERMISSION_READ], "view sales territories")?;
+        self.repository.list_territories(self.tenant_context.tenant_id).await
+    }
+
+    async fn get_territory(&self, id: Uuid) -> Result<Territory> {
+        self.require(&[PERMISSION_READ], "view sales territories")?;
+        self.repository
+            .get_territory(self.tenant_context.tenant_id, id)
+            .await?
+            .ok_or_else(|| MasterDataError::TerritoryNotFound { id: id.to_string() })
+    }
+
+    async fn create_territory(&self, request: TerritoryRequest) -> Result<Territory> {
+        self.require(&[], "create sales territories")?;
+        let territory = Territory::create(self.tenant_context.tenant_id, request, self.tenant_context.user_id, Utc::now())?;
+        self.repository.insert_territory(&territory).await?;
+        Ok(territory)
+    }
+
+    async fn update_territory(&self, id: Uuid, request: TerritoryRequest) -> Result<Territory> {
+        self.require(&[], "change sales territories")?;
+        let mut territory = self.get_territory(id).await?;
+        territory.apply(request, Utc::now())?;
+        self.repository.update_territory(&territory).await?;
+        Ok(territory)
+    }
+
+    async fn delete_territory(&self, id: Uuid) -> Result<()> {
+        self.require(&[], "delete sales territories")?;
+        if !self.repository.delete_territory(self.tenant_context.tenant_id, id).await? {
+            return Err(MasterDataError::TerritoryNotFound { id: id.to_string() });
+        }
+        Ok(())
+    }
+
+    async fn preview_matches(&self, rules: &TerritoryRules) -> Result<Vec<Uuid>> {
+        self.require(&[PERMISSION_READ], "view sales territories")?;
+        let customers = self.repository.customer_ownerships(self.tenant_context.tenant_id).await?;
+        Ok(customers
+            .iter()
+            .filter(|customer| rules.matches(&customer.profile))
+            .map(|customer| customer.profile.customer_id)
+            .collect())
+    }
+
+    async fn preview_reassignment(&self) -> Result<ReassignmentPlan> {
+        self.require(&[], "re-assign customers")?;
+        self.plan().await
+    }
+
+    async fn reassign(&self) -> Result<ReassignmentPlan> {
+        self.require(&[], "re-assign customers")?;
+        let plan = self.plan().await?;
+        let stored = self.apply(&plan).await?;
+        if stored < plan.assignments.len() {
+            tracing::warn!(
+                "Re-assignment skipped {} customers of tenant {} changed meanwhile",
+                plan.assignments.len() - stored,
+                self.tenant_context.tenant_id
+            );
+        }
+        Ok(plan)
+    }
+
+    async fn get_assignment(&self, customer_id: Uuid) -> Result<CustomerAssignment> {
+        self.require(&[PERMISSION_READ], "view customer assignments")?;
+        Ok(self.ownership(customer_id).await?.assignment)
+    }
+
+    async fn assign_customer(&self, customer_id: Uuid) -> Result<CustomerAssignment> {
+        let territories = self.repository.list_territories(self.tenant_context.tenant_id).await?;
+        let customer = self.ownership(customer_id).await?;
+        let plan = plan_reassignment(&territories, std::slice::from_ref(&customer));
+        self.apply(&plan).await?;
+        Ok(plan.assignments.first().map_or(customer.assignment, |planned| planned.after))
+    }
+
+    async fn set_overrides(&self, customer_id: Uuid, request: AssignmentOverrideRequest) -> Result<CustomerAssignment> {
+        self.require(&[PERMISSION_WRITE], "assign customers")?;
+
+        for _ in 0..OVERRIDE_ATTEMPTS {
+            let before = self.ownership(customer_id).await?.assignment;
+            let after = before.with_overrides(request.sales_representative_id, request.account_manager_id);
+            if after == before {
+                return Ok(after);
+            }
+            let plan = ReassignmentPlan {
+                customers_evaluated: 1,
+                assignments: vec![PlannedAssignment { customer_id, before, after }],
+                ..Default::default()
+            };
+            if self.apply(&plan).await? == 1 {
+                return Ok(after);
+            }
+        }
+        Err(MasterDataError::Internal {
+            message: format!("Assignment of customer {} kept changing", customer_id),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::IndustryClassification;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct MemoryRepository {
+        territories: Mutex<Vec<Territory>>,
+        customers: Mutex<Vec<CustomerOwnership>>,
+        changes: Mutex<Vec<OwnershipChange>>,
+    }
+
+    #[async_trait]
+    impl TerritoryRepository for MemoryRepository {
+        async fn list_territories(&self, tenant_id: Uuid) -> Result<Vec<Territory>> {
+            Ok(self.territories.lock().unwrap().iter().filter(|t| t.tenant_id == tenant_id).cloned().collect())
+        }
+
+        async fn get_territory(&self, tenant_id: Uuid, id: Uuid) -> Result<Option<Territory>> {
+            Ok(self.territories.lock().unwrap().iter().find(|t| t.tenant_id == tenant_id && t.id == id).cloned())
+        }
+
+        async fn insert_territory(&self, territory: &Territory) -> Result<()> {
+            self.territories.lock().unwrap().push(territory.clone());
+            Ok(())
+        }
+
+        async fn update_territory(&self, territory: &Territory) -> Result<()> {
+            let mut territories = self.territories.lock().unwrap();
+            let slot = territories.iter_mut().find(|t| t.id == territory.id).unwrap();
+            *slot = territory.clone();
+            Ok(())
+        }
+
+        async fn delete_territory(&self, tenant_id: Uuid, id: Uuid) -> Result<bool> {
+            let mut territories = self.territories.lock().unwrap();
+            let before = territories.len();
+            territories.retain(|t| !(t.tenant_id == tenant_id && t.id == id));
+            Ok(territories.len() < before)
+        }
+
+        async fn customer_ownerships(&self, _tenant_id: Uuid) -> Result<Vec<CustomerOwnership>> {
+            Ok(self.customers.lock().unwrap().clone())
+        }
+
+        async fn customer_ownership(&self, _tenant_id: Uuid, customer_id: Uuid) -> Result<Option<CustomerOwnership>> {
+            Ok(self.customers.lock().unwrap().iter().find(|c| c.profile.customer_id == customer_id).cloned())
+        }
+
+        async fn store_assignments(
+            &self,
+            _tenant_id: Uuid,
+            assignments: &[PlannedAssignment],
+            changes: &[OwnershipChange],
+        ) -> Result<usize> {
+            let mut customers = self.customers.lock().unwrap();
+            let mut stored = 0;
+            for planned in assignments {
+                let Some(customer) = customers
+                    .iter_mut()
+                    .find(|c| c.profile.customer_id == planned.customer_id && c.assignment == planned.before)
+                else {
+                    continue;
+                };
+                customer.assignment = planned.after;
+                stored += 1;
+                self.changes
+                    .lock()
+                    .unwrap()
+                    .extend(changes.iter().filter(|c| c.customer_id == planned.customer_id).cloned());
+            }
+            Ok(stored)
+        }
+    }
+
+    fn service(repo: &Arc<MemoryRepository>, permissions: &[&str]) -> DefaultTerritoryService {
+        let mut context = TenantContext::new(Uuid::nil(), "tenant".to_string(), Uuid::new_v4());
+        context.permissions = permissions.iter().map(|p| p.to_string()).collect();
+        DefaultTerritoryService::new(repo.clone(), context)
+    }
+
+    fn request(name: &str, priority: i32, countries: &[&str], rep: Uuid) -> TerritoryRequest {
+        TerritoryRequest {
+            name: name.to_string(),
+            description: None,
+            priority,
+            rules: TerritoryRules {
+                countries: countries.iter().map(|c| c.to_string()).collect(),
+                ..Default::default()
+            },
+            sales_representative_id: Some(rep),
+            account_manager_id: None,
+            is_active: true,
+        }
+    }
+
+    fn customer(country: &str) -> CustomerOwnership {
+        CustomerOwnership {
+            profile: CustomerProfile {
+                customer_id: Uuid::new_v4(),
+                country_code: Some(country.to_string()),
+                industry: Some(IndustryClassification::Retail),
+                ..Default::default()
+            },
+            assignment: CustomerAssignment::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_preview_and_apply_move_the_same_customers() {
+        let repo = Arc::new(MemoryRepository::default());
+        let (dach_rep, austria_rep, override_rep) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let manager = service(&repo, &[PERMISSION_MANAGE_TERRITORIES]);
+        let dach = manager.create_territory(request("DACH", 0, &["DE", "AT", "CH"], dach_rep)).await.unwrap();
+        *repo.customers.lock().unwrap() = vec![customer("DE"), customer("AT"), customer("AT"), customer("FR")];
+
+        let initial = manager.reassign().await.unwrap();
+        assert_eq!(initial.customers_moving, 3);
+        assert_eq!(repo.changes.lock().unwrap().len(), 3);
+
+        // One Austrian customer is kept by hand before Austria gets its own territory
+        let kept = repo.customers.lock().unwrap()[2].profile.customer_id;
+        let assignment = manager
+            .set_overrides(kept, AssignmentOverrideRequest { sales_representative_id: Some(override_rep), account_manager_id: None })
+            .await
+            .unwrap();
+        assert_eq!(assignment.sales_representative.source, AssignmentSource::Override);
+        manager.create_territory(request("Austria", 10, &["AT"], austria_rep)).await.unwrap();
+
+        let preview = manager.preview_reassignment().await.unwrap();
+        assert_eq!(preview.customers_moving, 1);
+        assert_eq!(preview.moves, vec![AssigneeMove {
+            role: AssignmentRole::SalesRepresentative,
+            from_assignee_id: Some(dach_rep),
+            to_assignee_id: Some(austria_rep),
+            customers: 1,
+        }]);
+        assert_eq!(preview.assignments.len(), 2, "the kept customer's derived assignee changes too");
+        assert_eq!(repo.changes.lock().unwrap().len(), 4, "previews change nothing");
+
+        let applied = manager.reassign().await.unwrap();
+        assert_eq!(applied.customers_moving, preview.customers_moving);
+        assert_eq!(applied.moves, preview.moves);
+        let changes = repo.changes.lock().unwrap().clone();
+        assert_eq!(changes.len(), 5);
+        assert_eq!(changes[4].new_assignee_id, Some(austria_rep));
+        assert_eq!(changes[4].source, AssignmentSource::Territory);
+        assert!(manager.preview_reassignment().await.unwrap().assignments.is_empty());
+
+        let kept = manager.get_assignment(kept).await.unwrap();
+        assert_eq!(kept.sales_representative.assignee_id, Some(override_rep));
+        assert_eq!(kept.sales_representative.derived_id, Some(austria_rep));
+        assert_ne!(kept.territory_id, Some(dach.id));
+    }
+
+    #[tokio::test]
+    async fn test_territories_need_the_manage_permission() {
+        let repo = Arc::new(MemoryRepository::default());
+        let reader = service(&repo, &[PERMISSION_READ]);
+
+        assert!(matches!(
+            reader.create_territory(request("DACH", 0, &["DE"], Uuid::new_v4())).await,
+            Err(MasterDataError::PermissionDenied { .. })
+        ));
+        assert!(reader.reassign().await.is_err());
+        assert!(reader.list_territories().await.unwrap().is_empty());
+        assert!(matches!(
+            reader.set_overrides(Uuid::new_v4(), AssignmentOverrideRequest::default()).await,
+            Err(MasterDataError::PermissionDenied { .. })
+        ));
+    }
+}
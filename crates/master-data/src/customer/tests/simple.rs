@@ -39,13 +39,11 @@ fn test_customer_validator_basic() {
     assert!(validator.validate_tags(&["tag1".to_string(), "tag1".to_string()]).is_err());
 }
 
-#[test]
-fn test_customer_creation() {
-    let customer_id = Uuid::new_v4();
-    let user_id = Uuid::new_v4();
+/// Fully populated B2B customer
+pub(crate) fn sample_customer(customer_id: Uuid, user_id: Uuid) -> Customer {
     let now = Utc::now();
 
-    let customer = Customer {
+    Customer {
         id: customer_id,
         customer_number: "TEST-001".to_string(),
         external_ids: std::collections::HashMap::new(),
@@ -85,11 +83,13 @@ fn test_customer_creation() {
             }),
             tax_exempt: false,
             tax_numbers: std::collections::HashMap::new(),
+            bank_accounts: Vec::new(),
         },
         price_group_id: None,
         discount_group_id: None,
         sales_representative_id: None,
         account_manager_id: None,
+        assignment: Default::default(),
         customer_segments: vec![CustomerSegment {
             segment_type: "BUSINESS_SIZE".to_string(),
             segment_value: "Enterprise".to_string(),
@@ -165,7 +165,15 @@ fn test_customer_creation() {
             deleted_at: None,
             deleted_by: None,
         },
-    };
+    }
+}
+
+#[test]
+fn test_customer_creation() {
+    let customer_id = Uuid::new_v4();
+    let user_id = Uuid::new_v4();
+
+    let customer = sample_customer(customer_id, user_id);
 
     // Basic assertions
     assert_eq!(customer.id, customer_id);
@@ -297,6 +305,7 @@ fn test_financial_info() {
         }),
         tax_exempt: false,
         tax_numbers: std::collections::HashMap::new(),
+        bank_accounts: Vec::new(),
     };
 
     assert_eq!(financial_info.currency_code, "USD");
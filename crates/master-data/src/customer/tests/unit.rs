@@ -193,6 +193,16 @@ async fn test_address_validation() {
     assert!(!valid_address.country_code.is_empty());
     assert!(valid_address.is_primary);
     assert!(valid_address.is_active);
+
+    // Country rules from the shared address type
+    use crate::customer::validation::CustomerValidator;
+    let validator = CustomerValidator::new();
+    assert!(validator.validate_address("address", &valid_address.postal_address()).is_ok());
+
+    let mut bad_zip = valid_address.clone();
+    bad_zip.postal_code = "1000".to_string();
+    let error = validator.validate_address("address", &bad_zip.postal_address()).unwrap_err();
+    assert!(error.to_string().contains("address.postal_code_format"));
 }
 
 #[tokio::test]
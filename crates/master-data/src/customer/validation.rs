@@ -3,6 +3,8 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use uuid::Uuid;
 
+use erp_core::AddressRule;
+
 use crate::customer::model::*;
 use crate::error::{MasterDataError, Result};
 
@@ -619,6 +621,29 @@ impl CustomerValidationEngine for DefaultCustomerValidationEngine {
             });
         }
 
+        for (i, address) in request.addresses.iter().flatten().enumerate() {
+            if let Err(violations) = address.postal_address().normalize_and_validate() {
+                errors.extend(violations.into_iter().map(|v| ValidationIssue {
+                    issue_type: match v.rule {
+                        AddressRule::LineRequired
+                        | AddressRule::CityRequired
+                        | AddressRule::RegionRequired
+                        | AddressRule::PostalCodeRequired => ValidationIssueType::RequiredField,
+                        _ => ValidationIssueType::InvalidFormat,
+                    },
+                    severity: ValidationSeverity::Error,
+                    field_path: format!("addresses[{}].{}", i, v.field),
+                    current_value: None,
+                    expected_value: None,
+                    error_code: v.rule.code().to_string(),
+                    message: v.message,
+                    description: None,
+                    remediation_steps: vec![],
+                    related_rules: vec![],
+                }));
+            }
+        }
+
         // Validate business rules
         let business_rule_results = self.execute_business_rules(&self.business_rules, &Customer::default()).await?;
 
@@ -894,6 +919,13 @@ impl CustomerValidator {
 
         Ok(())
     }
+
+    /// Normalize and validate an address against its country's rules; returns the normalized address
+    pub fn validate_address(&self, field: &str, address: &erp_core::Address) -> Result<erp_core::Address> {
+        address
+            .normalize_and_validate()
+            .map_err(|violations| MasterDataError::invalid_address(field, &violations))
+    }
 }
 
 impl Default for CustomerValidator {
@@ -902,6 +934,36 @@ impl Default for CustomerValidator {
     }
 }
 
+/// EU VAT id: country prefix and 8 to 12 characters (`DE123456789`)
+static VAT_ID_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^[A-Z]{2}[0-9A-Z+*]{8,12}$").unwrap()
+});
+
+/// US employer identification number (`12-3456789`)
+static EIN_REGEX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^\d{2}-?\d{7}$").unwrap()
+});
+
+/// Warn about tax numbers in no format that can be checked. They are stored
+/// as given; the caller gets a `TAX_ID_UNVERIFIED` warning per number.
+pub fn warn_unverifiable_tax_numbers(tax_numbers: &HashMap<String, String>) {
+    let mut kinds: Vec<&String> = tax_numbers.keys().collect();
+    kinds.sort();
+    for kind in kinds {
+        let value: String = tax_numbers[kind].chars().filter(|c| !c.is_whitespace()).collect();
+        if VAT_ID_REGEX.is_match(&value.to_uppercase()) || EIN_REGEX.is_match(&value) {
+            continue;
+        }
+        erp_core::warnings::warn(
+            erp_core::Warning::new(
+                erp_core::WarningCode::TaxIdUnverified,
+                format!("Tax number '{}' could not be verified and was stored as given", kind),
+            )
+            .with_field(format!("tax_numbers.{}", kind)),
+        );
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1405,11 +1467,13 @@ impl Default for Customer {
                 payment_terms: None,
                 tax_exempt: false,
                 tax_numbers: HashMap::new(),
+                bank_accounts: Vec::new(),
             },
             price_group_id: None,
             discount_group_id: None,
             sales_representative_id: None,
             account_manager_id: None,
+            assignment: Default::default(),
             customer_segments: vec![],
             acquisition_channel: None,
             customer_lifetime_value: None,
@@ -39,6 +39,30 @@ pub enum MasterDataError {
     #[error("Duplicate product number: {number}")]
     DuplicateProductNumber { number: String },
 
+    #[error("Customer communication not found: {id}")]
+    CommunicationNotFound { id: String },
+
+    #[error("Sales territory not found: {id}")]
+    TerritoryNotFound { id: String },
+
+    #[error("Serial number not found: {serial}")]
+    SerialNotFound { serial: String },
+
+    #[error("Duplicate serial number: {serial}")]
+    DuplicateSerialNumber { serial: String },
+
+    #[error("Serial {serial} cannot move from {from} to {to}")]
+    InvalidSerialTransition { serial: String, from: String, to: String },
+
+    #[error("Return {return_number} cannot move from {from} to {to}")]
+    InvalidReturnTransition { return_number: String, from: String, to: String },
+
+    #[error("Transfer {transfer_id} cannot move from {from} to {to}")]
+    InvalidTransferTransition { transfer_id: String, from: String, to: String },
+
+    #[error("Pick wave {wave_number} cannot move from {from} to {to}")]
+    InvalidWaveTransition { wave_number: String, from: String, to: String },
+
     #[error("Customer has active orders and cannot be deleted")]
     CustomerHasActiveOrders,
 
@@ -54,9 +78,24 @@ pub enum MasterDataError {
     #[error("Credit limit exceeded: {requested} > {limit}")]
     CreditLimitExceeded { requested: String, limit: String },
 
+    #[error("Location capacity exceeded: {location} would be at {utilization}%")]
+    LocationCapacityExceeded { location: String, utilization: String },
+
+    #[error("No exchange rate into {base_currency} effective on {date} for {currencies}")]
+    ExchangeRateMissing { base_currency: String, currencies: String, date: String },
+
+    #[error("An exchange rate {pair} effective on {date} already exists")]
+    DuplicateExchangeRate { pair: String, date: String },
+
     #[error("Validation error: {field}: {message}")]
     ValidationError { field: String, message: String },
 
+    #[error("Permission denied: {action}")]
+    PermissionDenied { action: String },
+
+    #[error("Cannot decrypt {field} sealed with key {key_id}: {reason}")]
+    FieldDecryptionFailed { field: String, key_id: String, reason: String },
+
     #[error("Data quality issue: {entity_type}: {entity_id}: {issue}")]
     DataQualityIssue {
         entity_type: String,
@@ -96,6 +135,16 @@ pub enum MasterDataError {
 
 pub type Result<T> = std::result::Result<T, MasterDataError>;
 
+impl MasterDataError {
+    /// Validation error listing every address rule that was broken
+    pub fn invalid_address(field: &str, violations: &[erp_core::AddressViolation]) -> Self {
+        MasterDataError::ValidationError {
+            field: field.to_string(),
+            message: violations.iter().map(ToString::to_string).collect::<Vec<_>>().join("; "),
+        }
+    }
+}
+
 // Implement conversion to HTTP status codes for web handlers
 #[cfg(feature = "axum")]
 impl axum::response::IntoResponse for MasterDataError {
@@ -110,6 +159,9 @@ impl axum::response::IntoResponse for MasterDataError {
             | MasterDataError::ProductNotFound { .. }
             | MasterDataError::LocationNotFound { .. }
             | MasterDataError::OrganizationUnitNotFound { .. }
+            | MasterDataError::SerialNotFound { .. }
+            | MasterDataError::CommunicationNotFound { .. }
+            | MasterDataError::TerritoryNotFound { .. }
             | MasterDataError::NotFound => {
                 (StatusCode::NOT_FOUND, self.to_string())
             }
@@ -123,9 +175,20 @@ impl axum::response::IntoResponse for MasterDataError {
                 (StatusCode::BAD_REQUEST, self.to_string())
             }
 
+            MasterDataError::PermissionDenied { .. } => {
+                (StatusCode::FORBIDDEN, self.to_string())
+            }
+
             MasterDataError::DuplicateCustomerNumber { .. }
             | MasterDataError::DuplicateSupplierNumber { .. }
-            | MasterDataError::DuplicateProductNumber { .. } => {
+            | MasterDataError::DuplicateProductNumber { .. }
+            | MasterDataError::DuplicateSerialNumber { .. }
+            | MasterDataError::InvalidSerialTransition { .. }
+            | MasterDataError::InvalidReturnTransition { .. }
+            | MasterDataError::InvalidTransferTransition { .. }
+            | MasterDataError::InvalidWaveTransition { .. }
+            | MasterDataError::LocationCapacityExceeded { .. }
+            | MasterDataError::DuplicateExchangeRate { .. } => {
                 (StatusCode::CONFLICT, self.to_string())
             }
 
@@ -152,7 +215,7 @@ impl axum::response::IntoResponse for MasterDataError {
                 (StatusCode::INTERNAL_SERVER_ERROR, "Serialization error".to_string())
             }
 
-            MasterDataError::DataQualityIssue { .. } => {
+            MasterDataError::DataQualityIssue { .. } | MasterDataError::ExchangeRateMissing { .. } => {
                 (StatusCode::UNPROCESSABLE_ENTITY, self.to_string())
             }
 
@@ -160,6 +223,10 @@ impl axum::response::IntoResponse for MasterDataError {
                 (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
             }
 
+            MasterDataError::FieldDecryptionFailed { .. } => {
+                (StatusCode::INTERNAL_SERVER_ERROR, self.to_string())
+            }
+
 
             MasterDataError::NotFoundError(_) => {
                 (StatusCode::NOT_FOUND, self.to_string())
@@ -0,0 +1,471 @@
+//! # Available to Promise
+//!
+//! Answers how much of a product can still be promised on a given day, and
+//! from which day a quantity can be promised. The timeline starts with
+//! today's stock on hand at sellable locations minus what is reserved there,
+//! which is exactly what a reservation made now may take (see
+//! [`StockAvailability::available_to_promise`](super::model::StockAvailability::available_to_promise)).
+//! Reservations and transfers waiting to be shipped out already hold stock on
+//! hand, so they are the demand promised so far; batches that failed
+//! inspection or were recalled hold theirs back until they are dispositioned.
+//!
+//! Scheduled receipts add to the timeline on their expected day:
+//!
+//! - open purchase order lines by the order's expected delivery date, while
+//!   the order is in one of [`PURCHASE_ORDER_STATUSES`];
+//! - inbound transfers by their expected arrival, while the transfer is in
+//!   one of [`TRANSFER_STATUSES`].
+//!
+//! Receipts that are overdue count from today. Receipts without an expected
+//! day, to a location whose stock is not sellable or in any other status are
+//! listed with the reason they were left out, but not promised against.
+//!
+//! The quantity available on a day is the lowest balance from that day on,
+//! so promising it never takes stock that a later promise already counts on.
+//! [`AtpTimeline::promise`] books demand onto the timeline, which lets a
+//! batch of order lines be checked in the order they were entered.
+//!
+//! The functions here are pure; the inputs are read by
+//! [`AvailableToPromiseRepository`](super::repository::AvailableToPromiseRepository)
+//! and the calculation is driven by
+//! [`AvailableToPromiseService`](super::service::AvailableToPromiseService).
+
+use crate::inventory::scenario::SupplySource;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+/// Purchase order statuses whose open lines are expected to arrive
+pub const PURCHASE_ORDER_STATUSES: &[&str] = &["submitted", "approved", "ordered", "partially_received", "pending"];
+
+/// Transfer statuses whose outstanding quantity is expected at the target location
+pub const TRANSFER_STATUSES: &[&str] = &["approved", "pending", "in_transit", "partially_received"];
+
+/// Most lines accepted by one batch request
+pub const MAX_BATCH_LINES: usize = 200;
+
+/// Stock of the product at one location
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AtpStock {
+    pub location_id: Uuid,
+    pub quantity_available: i64,
+    pub quantity_reserved: i64,
+    /// Units in failed or recalled batches awaiting disposition
+    pub quantity_blocked: i64,
+    pub sellable: bool,
+}
+
+/// Outstanding quantity of a purchase order line or an inbound transfer
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScheduledReceipt {
+    pub source: SupplySource,
+    pub reference_id: Uuid,
+    pub location_id: Uuid,
+    pub status: String,
+    pub quantity: i64,
+    pub expected_date: Option<NaiveDate>,
+    /// Whether stock at the receiving location may be sold
+    pub sellable: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AtpElementKind {
+    OnHand,
+    Reserved,
+    /// Failed or recalled batches awaiting disposition
+    Blocked,
+    PurchaseOrder,
+    Transfer,
+    /// Demand promised by an earlier line of the same batch
+    Promised,
+}
+
+/// One supply or demand behind the timeline; demand has a negative quantity
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AtpElement {
+    pub kind: AtpElementKind,
+    pub reference_id: Option<Uuid>,
+    pub location_id: Option<Uuid>,
+    /// Day the element counts from; `None` if it has no expected day
+    pub date: Option<NaiveDate>,
+    pub quantity: i64,
+    pub status: Option<String>,
+    pub counted: bool,
+    /// Why the element was not counted, or only partly
+    pub note: Option<String>,
+}
+
+/// Balance after all elements of a day
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AtpPoint {
+    pub date: NaiveDate,
+    /// Net change of the day
+    pub change: i64,
+    pub balance: i64,
+    /// What can still be promised for this day without short-changing a later one
+    pub available: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AtpTimeline {
+    pub product_id: Uuid,
+    /// `None` when aggregated across all sellable locations
+    pub location_id: Option<Uuid>,
+    pub as_of: NaiveDate,
+    pub elements: Vec<AtpElement>,
+    pub points: Vec<AtpPoint>,
+}
+
+impl AtpTimeline {
+    /// Build the timeline from today's stock and the scheduled receipts
+    pub fn build(
+        product_id: Uuid,
+        location_id: Option<Uuid>,
+        today: NaiveDate,
+        stock: &[AtpStock],
+        receipts: &[ScheduledReceipt],
+    ) -> Self {
+        let mut elements = Vec::new();
+
+        for entry in stock {
+            let not_sellable = (!entry.sellable).then(|| "stock at this location is not sellable".to_string());
+            elements.push(AtpElement {
+                kind: AtpElementKind::OnHand,
+                reference_id: None,
+                location_id: Some(entry.location_id),
+                date: Some(today),
+                quantity: entry.quantity_available,
+                status: None,
+                counted: entry.sellable,
+                note: not_sellable.clone(),
+            });
+            if entry.quantity_reserved > 0 {
+                // A location cannot give away more than it holds, however much is reserved there
+                let held = entry.quantity_reserved.min(entry.quantity_available.max(0));
+                let note = match &not_sellable {
+                    Some(note) => Some(note.clone()),
+                    None if held < entry.quantity_reserved => Some(format!(
+                        "reservations exceed stock on hand by {}",
+                        entry.quantity_reserved - held
+                    )),
+                    None => None,
+                };
+                elements.push(AtpElement {
+                    kind: AtpElementKind::Reserved,
+                    reference_id: None,
+                    location_id: Some(entry.location_id),
+                    date: Some(today),
+                    quantity: -held,
+                    status: None,
+                    counted: entry.sellable,
+                    note,
+                });
+            }
+            if entry.quantity_blocked > 0 {
+                let free = (entry.quantity_available - entry.quantity_reserved).max(0);
+                elements.push(AtpElement {
+                    kind: AtpElementKind::Blocked,
+                    reference_id: None,
+                    location_id: Some(entry.location_id),
+                    date: Some(today),
+                    quantity: -entry.quantity_blocked.min(free),
+                    status: None,
+                    counted: entry.sellable,
+                    note: not_sellable.clone(),
+                });
+            }
+        }
+
+        for receipt in receipts {
+            let (kind, statuses) = match receipt.source {
+                SupplySource::PurchaseOrder => (AtpElementKind::PurchaseOrder, PURCHASE_ORDER_STATUSES),
+                SupplySource::Transfer => (AtpElementKind::Transfer, TRANSFER_STATUSES),
+            };
+            let note = if !statuses.contains(&receipt.status.as_str()) {
+                Some(format!("status {} is not expected to arrive", receipt.status))
+            } else if !receipt.sellable {
+                Some("stock at this location is not sellable".to_string())
+            } else if receipt.expected_date.is_none() {
+                Some("no expected date".to_string())
+            } else {
+                None
+            };
+            elements.push(AtpElement {
+                kind,
+                reference_id: Some(receipt.reference_id),
+                location_id: Some(receipt.location_id),
+                // Overdue receipts are expected any moment now
+                date: receipt.expected_date.map(|date| date.max(today)),
+                quantity: receipt.quantity,
+                status: Some(receipt.status.clone()),
+                counted: note.is_none(),
+                note,
+            });
+        }
+
+        let mut timeline = Self { product_id, location_id, as_of: today, elements, points: Vec::new() };
+        timeline.recalculate();
+        timeline
+    }
+
+    /// Quantity that can be promised for delivery on `date`
+    pub fn available_on(&self, date: NaiveDate) -> i64 {
+        let date = date.max(self.as_of);
+        self.points
+            .iter()
+            .rev()
+            .find(|point| point.date <= date)
+            .map(|point| point.available)
+            .unwrap_or(0)
+    }
+
+    /// First day from which `quantity` can be promised, if any
+    pub fn earliest_date(&self, quantity: i64) -> Option<NaiveDate> {
+        if quantity <= 0 {
+            return Some(self.as_of);
+        }
+        // `available` never decreases along the timeline, so the first day that suffices is the answer
+        self.points.iter().find(|point| point.available >= quantity).map(|point| point.date)
+    }
+
+    /// Book `quantity` as promised on `date`
+    pub fn promise(&mut self, quantity: i64, date: NaiveDate, reference_id: Option<Uuid>) {
+        self.elements.push(AtpElement {
+            kind: AtpElementKind::Promised,
+            reference_id,
+            location_id: self.location_id,
+            date: Some(date.max(self.as_of)),
+            quantity: -quantity,
+            status: None,
+            counted: true,
+            note: None,
+        });
+        self.recalculate();
+    }
+
+    fn recalculate(&mut self) {
+        let mut changes: BTreeMap<NaiveDate, i64> = BTreeMap::new();
+        changes.insert(self.as_of, 0);
+        for element in self.elements.iter().filter(|element| element.counted) {
+            if let Some(date) = element.date {
+                *changes.entry(date).or_insert(0) += element.quantity;
+            }
+        }
+
+        let mut balance = 0;
+        let mut points: Vec<AtpPoint> = changes
+            .into_iter()
+            .map(|(date, change)| {
+                balance += change;
+                AtpPoint { date, change, balance, available: 0 }
+            })
+            .collect();
+
+        let mut lowest_ahead = i64::MAX;
+        for point in points.iter_mut().rev() {
+            lowest_ahead = lowest_ahead.min(point.balance);
+            point.available = lowest_ahead.max(0);
+        }
+        self.points = points;
+    }
+}
+
+/// One line of `POST /inventory/atp/batch`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AtpBatchLine {
+    pub product_id: Uuid,
+    #[serde(default)]
+    pub location_id: Option<Uuid>,
+    pub quantity: i64,
+    /// Requested delivery day; today if not given
+    #[serde(default)]
+    pub date: Option<NaiveDate>,
+    /// Order line the quantity is for, echoed in the result
+    #[serde(default)]
+    pub reference_id: Option<Uuid>,
+}
+
+/// Answer for one line, after all earlier lines of the batch were promised
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AtpLineResult {
+    pub product_id: Uuid,
+    pub location_id: Option<Uuid>,
+    pub reference_id: Option<Uuid>,
+    pub quantity: i64,
+    pub date: NaiveDate,
+    pub available_on_date: i64,
+    pub can_promise: bool,
+    pub earliest_date: Option<NaiveDate>,
+}
+
+/// Check `line` against `timeline` and book it on the day it can be promised
+pub fn promise_line(timeline: &mut AtpTimeline, line: &AtpBatchLine) -> AtpLineResult {
+    let date = line.date.unwrap_or(timeline.as_of).max(timeline.as_of);
+    let available_on_date = timeline.available_on(date);
+    let can_promise = available_on_date >= line.quantity;
+    let earliest_date = if can_promise { Some(date) } else { timeline.earliest_date(line.quantity) };
+
+    // Later lines must not count on what this one takes, whenever it ships
+    if let Some(promised_on) = earliest_date {
+        timeline.promise(line.quantity, promised_on, line.reference_id);
+    }
+
+    AtpLineResult {
+        product_id: line.product_id,
+        location_id: line.location_id,
+        reference_id: line.reference_id,
+        quantity: line.quantity,
+        date,
+        available_on_date,
+        can_promise,
+        earliest_date,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 3, d).unwrap()
+    }
+
+    fn stock(location_id: Uuid, available: i64, reserved: i64) -> AtpStock {
+        AtpStock { location_id, quantity_available: available, quantity_reserved: reserved, quantity_blocked: 0, sellable: true }
+    }
+
+    fn receipt(source: SupplySource, location_id: Uuid, status: &str, quantity: i64, date: Option<NaiveDate>) -> ScheduledReceipt {
+        ScheduledReceipt {
+            source,
+            reference_id: Uuid::new_v4(),
+            location_id,
+            status: status.to_string(),
+            quantity,
+            expected_date: date,
+            sellable: true,
+        }
+    }
+
+    #[test]
+    fn test_overlapping_inbound_and_outbound() {
+        let location = Uuid::new_v4();
+        let mut timeline = AtpTimeline::build(
+            Uuid::new_v4(),
+            Some(location),
+            day(1),
+            &[stock(location, 120, 20)],
+            &[
+                receipt(SupplySource::PurchaseOrder, location, "ordered", 200, Some(day(5))),
+                receipt(SupplySource::Transfer, location, "in_transit", 50, Some(day(3))),
+            ],
+        );
+        assert_eq!(timeline.available_on(day(1)), 100);
+        assert_eq!(timeline.available_on(day(3)), 150);
+        assert_eq!(timeline.available_on(day(10)), 350);
+
+        // Promised on the 4th, before the purchase order arrives: it eats into
+        // what was free before, and only the order replenishes it
+        timeline.promise(130, day(4), None);
+        assert_eq!(timeline.available_on(day(1)), 20);
+        assert_eq!(timeline.available_on(day(3)), 20);
+        assert_eq!(timeline.available_on(day(4)), 20);
+        assert_eq!(timeline.available_on(day(5)), 220);
+
+        // More than is there until the order arrives is not promised today
+        let line = AtpBatchLine {
+            product_id: timeline.product_id,
+            location_id: Some(location),
+            quantity: 50,
+            date: Some(day(2)),
+            reference_id: None,
+        };
+        let result = promise_line(&mut timeline, &line);
+        assert!(!result.can_promise);
+        assert_eq!(result.available_on_date, 20);
+        assert_eq!(result.earliest_date, Some(day(5)));
+        assert_eq!(timeline.available_on(day(5)), 170);
+    }
+
+    #[test]
+    fn test_earliest_date_is_the_inverse_of_available_on() {
+        let location = Uuid::new_v4();
+        let timeline = AtpTimeline::build(
+            Uuid::new_v4(),
+            None,
+            day(10),
+            &[stock(location, 30, 0)],
+            &[
+                receipt(SupplySource::PurchaseOrder, location, "approved", 40, Some(day(4))),
+                receipt(SupplySource::PurchaseOrder, location, "ordered", 100, Some(day(20))),
+                receipt(SupplySource::Transfer, location, "in_transit", 25, None),
+            ],
+        );
+        // The overdue order counts from today, the undated transfer not at all
+        assert_eq!(timeline.available_on(day(10)), 70);
+        assert_eq!(timeline.earliest_date(70), Some(day(10)));
+        assert_eq!(timeline.earliest_date(71), Some(day(20)));
+        assert_eq!(timeline.earliest_date(171), None);
+
+        for quantity in [1, 70, 71, 170] {
+            let earliest = timeline.earliest_date(quantity).unwrap();
+            assert!(timeline.available_on(earliest) >= quantity);
+            assert!(earliest == timeline.as_of || timeline.available_on(earliest.pred_opt().unwrap()) < quantity);
+        }
+        let undated = timeline.elements.iter().find(|element| element.kind == AtpElementKind::Transfer).unwrap();
+        assert!(!undated.counted);
+    }
+
+    #[test]
+    fn test_cancelled_purchase_orders_are_not_promised_against() {
+        let location = Uuid::new_v4();
+        let quarantine = Uuid::new_v4();
+        let mut quarantined = stock(quarantine, 500, 0);
+        quarantined.sellable = false;
+        let timeline = AtpTimeline::build(
+            Uuid::new_v4(),
+            None,
+            day(1),
+            &[stock(location, 10, 15), quarantined],
+            &[
+                receipt(SupplySource::PurchaseOrder, location, "cancelled", 300, Some(day(2))),
+                receipt(SupplySource::PurchaseOrder, location, "draft", 300, Some(day(2))),
+                receipt(SupplySource::PurchaseOrder, location, "submitted", 5, Some(day(2))),
+            ],
+        );
+        // Over-reserved stock counts as nothing, not as a shortage elsewhere
+        assert_eq!(timeline.available_on(day(1)), 0);
+        assert_eq!(timeline.available_on(day(2)), 5);
+        assert_eq!(timeline.earliest_date(6), None);
+
+        let excluded: Vec<_> = timeline.elements.iter().filter(|element| !element.counted).collect();
+        assert_eq!(excluded.len(), 3);
+        assert!(excluded
+            .iter()
+            .any(|element| element.status.as_deref() == Some("cancelled") && element.note.is_some()));
+    }
+
+    #[test]
+    fn test_failed_batches_are_held_back_until_dispositioned() {
+        let location = Uuid::new_v4();
+        let mut held = stock(location, 100, 30);
+        held.quantity_blocked = 40;
+        let timeline = AtpTimeline::build(
+            Uuid::new_v4(),
+            Some(location),
+            day(1),
+            &[held.clone()],
+            &[receipt(SupplySource::PurchaseOrder, location, "ordered", 20, Some(day(3)))],
+        );
+        assert_eq!(timeline.available_on(day(1)), 30);
+        assert_eq!(timeline.available_on(day(3)), 50);
+
+        // Blocked units beyond what is left after reservations take nothing more
+        held.quantity_blocked = 90;
+        let timeline = AtpTimeline::build(Uuid::new_v4(), Some(location), day(1), &[held], &[]);
+        assert_eq!(timeline.available_on(day(1)), 0);
+        let blocked = timeline.elements.iter().find(|element| element.kind == AtpElementKind::Blocked).unwrap();
+        assert_eq!(blocked.quantity, -70);
+    }
+}
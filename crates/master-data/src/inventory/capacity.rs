@@ -0,0 +1,580 @@
+//! # Location Capacity
+//!
+//! A location, or one of its bins, can have a [`LocationCapacity`] in one of
+//! three [`CapacityUnit`]s: pallet positions, cubic meters or plain units.
+//! How much of it one unit of a product takes is its [`SpaceFactor`]:
+//!
+//! - an explicit override per product and capacity unit, if there is one
+//! - otherwise derived from the product's dimensions: its volume in cubic
+//!   meters, or that volume over the volume of one pallet position
+//! - otherwise the configured default volume, flagged as estimated so the
+//!   caller can warn about it
+//!
+//! Receipts and transfers are checked against the projected occupancy: the
+//! stock already at the location, stock in transit to it, and the incoming
+//! quantity. Reaching [`CapacitySettings::warn_percent`] gives a warning; going
+//! above 100% is refused unless the caller asks to override it and holds
+//! [`CAPACITY_OVERRIDE_PERMISSION`]. Locations without a capacity are never
+//! checked.
+
+use crate::error::{MasterDataError, Result};
+use erp_core::warnings::{Warning, WarningCode};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use uuid::Uuid;
+
+/// Permission required to fill a location or bin past its capacity
+pub const CAPACITY_OVERRIDE_PERMISSION: &str = "inventory:capacity_override";
+
+/// Transfer statuses whose stock is still on its way to the target location
+pub const INBOUND_TRANSFER_STATUSES: &[&str] =
+    &["pending_approval", "requested", "approved", "pending", "in_transit", "partially_received"];
+
+/// What a capacity is measured in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CapacityUnit {
+    PalletPositions,
+    CubicMeters,
+    Units,
+}
+
+impl CapacityUnit {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CapacityUnit::PalletPositions => "pallet_positions",
+            CapacityUnit::CubicMeters => "cubic_meters",
+            CapacityUnit::Units => "units",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "pallet_positions" => Some(CapacityUnit::PalletPositions),
+            "cubic_meters" => Some(CapacityUnit::CubicMeters),
+            "units" => Some(CapacityUnit::Units),
+            _ => None,
+        }
+    }
+}
+
+/// Storage capacity of a location, or of one bin when `bin_code` is set
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LocationCapacity {
+    pub location_id: Uuid,
+    pub bin_code: Option<String>,
+    pub unit: CapacityUnit,
+    pub capacity: f64,
+}
+
+/// A capacity as entered for a location
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocationCapacityInput {
+    pub bin_code: Option<String>,
+    pub unit: CapacityUnit,
+    pub capacity: f64,
+}
+
+/// Validate the capacities of a location: positive, and at most one per bin
+pub fn capacities_for(location_id: Uuid, inputs: Vec<LocationCapacityInput>) -> Result<Vec<LocationCapacity>> {
+    let mut seen = BTreeSet::new();
+    inputs
+        .into_iter()
+        .map(|input| {
+            let bin_code = input.bin_code.map(|bin| bin.trim().to_string()).filter(|bin| !bin.is_empty());
+            if !input.capacity.is_finite() || input.capacity <= 0.0 {
+                return Err(MasterDataError::ValidationError {
+                    field: "capacity".to_string(),
+                    message: "Capacity must be positive".to_string(),
+                });
+            }
+            if !seen.insert(bin_code.clone()) {
+                return Err(MasterDataError::ValidationError {
+                    field: "bin_code".to_string(),
+                    message: format!("More than one capacity for {}", describe(bin_code.as_deref())),
+                });
+            }
+            Ok(LocationCapacity {
+                location_id,
+                bin_code,
+                unit: input.unit,
+                capacity: input.capacity,
+            })
+        })
+        .collect()
+}
+
+/// Thresholds and defaults of the capacity checks
+#[derive(Debug, Clone, PartialEq)]
+pub struct CapacitySettings {
+    /// Utilization from which a receipt or transfer gives a warning
+    pub warn_percent: f64,
+    /// Volume of one pallet position
+    pub pallet_volume_m3: f64,
+    /// Volume of one unit of a product without dimensions
+    pub default_unit_volume_m3: f64,
+}
+
+impl Default for CapacitySettings {
+    fn default() -> Self {
+        Self {
+            warn_percent: 85.0,
+            pallet_volume_m3: 1.5,
+            default_unit_volume_m3: 0.01,
+        }
+    }
+}
+
+/// What the space of a product is derived from
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProductSpace {
+    pub product_id: Uuid,
+    pub length_cm: Option<f64>,
+    pub width_cm: Option<f64>,
+    pub height_cm: Option<f64>,
+    /// Explicit space per unit, by capacity unit
+    #[serde(default)]
+    pub overrides: HashMap<CapacityUnit, f64>,
+}
+
+/// Space one unit of a product takes in a capacity unit
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpaceFactor {
+    pub per_unit: f64,
+    /// The product has no dimensions; the configured default was used
+    pub estimated: bool,
+}
+
+impl ProductSpace {
+    /// Volume of one unit in cubic meters, if all dimensions are known
+    pub fn volume_m3(&self) -> Option<f64> {
+        let (length, width, height) = (self.length_cm?, self.width_cm?, self.height_cm?);
+        (length > 0.0 && width > 0.0 && height > 0.0).then(|| length * width * height / 1_000_000.0)
+    }
+
+    pub fn space_factor(&self, unit: CapacityUnit, settings: &CapacitySettings) -> SpaceFactor {
+        if let Some(per_unit) = self.overrides.get(&unit) {
+            return SpaceFactor { per_unit: *per_unit, estimated: false };
+        }
+        if unit == CapacityUnit::Units {
+            return SpaceFactor { per_unit: 1.0, estimated: false };
+        }
+        let (volume, estimated) = match self.volume_m3() {
+            Some(volume) => (volume, false),
+            None => (settings.default_unit_volume_m3, true),
+        };
+        let per_unit = match unit {
+            CapacityUnit::PalletPositions => volume / settings.pallet_volume_m3,
+            _ => volume,
+        };
+        SpaceFactor { per_unit, estimated }
+    }
+}
+
+/// Validate explicit space factors of a product
+pub fn validate_overrides(overrides: &HashMap<CapacityUnit, f64>) -> Result<()> {
+    match overrides.iter().find(|(_, per_unit)| !per_unit.is_finite() || **per_unit <= 0.0) {
+        Some((unit, _)) => Err(MasterDataError::ValidationError {
+            field: unit.as_str().to_string(),
+            message: "Space per unit must be positive".to_string(),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Units of a product at a location, or on their way there
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoredQuantity {
+    pub product_id: Uuid,
+    pub bin_code: Option<String>,
+    pub quantity: i64,
+}
+
+/// Units a receipt or transfer brings to a location; without a bin they land
+/// in the bin the product is already kept in
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IncomingQuantity {
+    pub product_id: Uuid,
+    pub bin_code: Option<String>,
+    pub quantity: i64,
+}
+
+/// How full a location or bin is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CapacityLevel {
+    Ok,
+    Warning,
+    Exceeded,
+}
+
+/// Occupancy of one capacity, in its unit
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CapacityUsage {
+    pub location_id: Uuid,
+    pub bin_code: Option<String>,
+    pub unit: CapacityUnit,
+    pub capacity: f64,
+    /// Space of the stock at the location and in transit to it
+    pub occupied: f64,
+    /// Space of the receipt or transfer being checked
+    pub incoming: f64,
+    pub utilization_percent: f64,
+    pub level: CapacityLevel,
+}
+
+impl CapacityUsage {
+    /// Space left after the incoming stock, never negative
+    pub fn free(&self) -> f64 {
+        (self.capacity - self.occupied - self.incoming).max(0.0)
+    }
+}
+
+/// Occupancy of every capacity of a location
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CapacityAssessment {
+    pub location_id: Uuid,
+    pub usages: Vec<CapacityUsage>,
+    /// Products counted with the default space because they have no dimensions
+    pub estimated_products: Vec<Uuid>,
+}
+
+impl CapacityAssessment {
+    pub fn exceeded(&self) -> impl Iterator<Item = &CapacityUsage> {
+        self.usages.iter().filter(|usage| usage.level == CapacityLevel::Exceeded)
+    }
+}
+
+fn describe(bin_code: Option<&str>) -> String {
+    match bin_code {
+        Some(bin) => format!("bin {}", bin),
+        None => "the location".to_string(),
+    }
+}
+
+/// Occupancy of each capacity of `location_id` once `incoming` arrives.
+/// Products missing from `products` count with the default space.
+pub fn assess(
+    location_id: Uuid,
+    capacities: &[LocationCapacity],
+    stored: &[StoredQuantity],
+    incoming: &[IncomingQuantity],
+    products: &HashMap<Uuid, ProductSpace>,
+    settings: &CapacitySettings,
+) -> CapacityAssessment {
+    let mut estimated = BTreeSet::new();
+    let mut space = |product_id: Uuid, unit: CapacityUnit| {
+        let factor = match products.get(&product_id) {
+            Some(product) => product.space_factor(unit, settings),
+            None => ProductSpace { product_id, ..Default::default() }.space_factor(unit, settings),
+        };
+        if factor.estimated {
+            estimated.insert(product_id);
+        }
+        factor.per_unit
+    };
+    let current_bin = |product_id: Uuid| {
+        stored
+            .iter()
+            .find(|item| item.product_id == product_id && item.bin_code.is_some())
+            .and_then(|item| item.bin_code.clone())
+    };
+
+    let usages = capacities
+        .iter()
+        .filter(|capacity| capacity.location_id == location_id)
+        .map(|capacity| {
+            let in_scope = |bin_code: &Option<String>| capacity.bin_code.is_none() || *bin_code == capacity.bin_code;
+            let occupied: f64 = stored
+                .iter()
+                .filter(|item| in_scope(&item.bin_code))
+                .map(|item| item.quantity.max(0) as f64 * space(item.product_id, capacity.unit))
+                .sum();
+            let incoming: f64 = incoming
+                .iter()
+                .filter(|item| in_scope(&item.bin_code.clone().or_else(|| current_bin(item.product_id))))
+                .map(|item| item.quantity.max(0) as f64 * space(item.product_id, capacity.unit))
+                .sum();
+            let utilization_percent = (occupied + incoming) / capacity.capacity * 100.0;
+            let level = if utilization_percent > 100.0 {
+                CapacityLevel::Exceeded
+            } else if utilization_percent >= settings.warn_percent {
+                CapacityLevel::Warning
+            } else {
+                CapacityLevel::Ok
+            };
+            CapacityUsage {
+                location_id,
+                bin_code: capacity.bin_code.clone(),
+                unit: capacity.unit,
+                capacity: capacity.capacity,
+                occupied,
+                incoming,
+                utilization_percent,
+                level,
+            }
+        })
+        .collect();
+
+    CapacityAssessment {
+        location_id,
+        usages,
+        estimated_products: estimated.into_iter().collect(),
+    }
+}
+
+/// Decide whether incoming stock may be stored: the warnings to give, or an
+/// error when a capacity would be exceeded without a permitted override
+pub fn enforce(assessment: &CapacityAssessment, override_requested: bool, may_override: bool) -> Result<Vec<Warning>> {
+    if let Some(usage) = assessment.exceeded().next() {
+        if !override_requested {
+            return Err(MasterDataError::LocationCapacityExceeded {
+                location: format!("{} of location {}", describe(usage.bin_code.as_deref()), assessment.location_id),
+                utilization: format!("{:.1}", usage.utilization_percent),
+            });
+        }
+        if !may_override {
+            return Err(MasterDataError::PermissionDenied {
+                action: "exceed location capacity".to_string(),
+            });
+        }
+    }
+
+    let mut warnings: Vec<Warning> = assessment
+        .usages
+        .iter()
+        .filter(|usage| usage.level != CapacityLevel::Ok)
+        .map(|usage| {
+            let (code, verb) = match usage.level {
+                CapacityLevel::Exceeded => (WarningCode::CapacityOverridden, "over capacity by override"),
+                _ => (WarningCode::LocationNearCapacity, "near capacity"),
+            };
+            Warning::new(
+                code,
+                format!(
+                    "{} is {} at {:.1}% of {} {}",
+                    describe(usage.bin_code.as_deref()),
+                    verb,
+                    usage.utilization_percent,
+                    usage.capacity,
+                    usage.unit.as_str()
+                ),
+            )
+        })
+        .collect();
+    if !assessment.usages.is_empty() {
+        warnings.extend(assessment.estimated_products.iter().map(|product_id| {
+            Warning::new(
+                WarningCode::SpaceFactorDefaulted,
+                format!("Product {} has no dimensions; its storage space was estimated", product_id),
+            )
+            .with_field("product_id")
+        }));
+    }
+    Ok(warnings)
+}
+
+/// Units of a product that still fit into the location-wide capacities of an
+/// assessment; `None` when the location has no capacity
+pub fn units_that_fit(assessment: &CapacityAssessment, product: &ProductSpace, settings: &CapacitySettings) -> Option<i64> {
+    assessment
+        .usages
+        .iter()
+        .filter(|usage| usage.bin_code.is_none())
+        .map(|usage| {
+            let per_unit = product.space_factor(usage.unit, settings).per_unit;
+            if per_unit <= 0.0 {
+                i64::MAX
+            } else {
+                (usage.free() / per_unit).floor() as i64
+            }
+        })
+        .min()
+}
+
+/// Cap the quantities proposed to move into a location, in proposal order,
+/// so that together they fit its free capacity
+pub fn fit_into_free_capacity(
+    location_id: Uuid,
+    capacities: &[LocationCapacity],
+    stored: &[StoredQuantity],
+    proposals: &[(Uuid, i64)],
+    products: &HashMap<Uuid, ProductSpace>,
+    settings: &CapacitySettings,
+) -> Vec<i64> {
+    let mut planned: Vec<IncomingQuantity> = Vec::new();
+    proposals
+        .iter()
+        .map(|&(product_id, quantity)| {
+            let assessment = assess(location_id, capacities, stored, &planned, products, settings);
+            let product = products
+                .get(&product_id)
+                .cloned()
+                .unwrap_or_else(|| ProductSpace { product_id, ..Default::default() });
+            let fitting = units_that_fit(&assessment, &product, settings).map_or(quantity, |fit| quantity.min(fit));
+            if fitting > 0 {
+                planned.push(IncomingQuantity { product_id, bin_code: None, quantity: fitting });
+            }
+            fitting
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn boxed(product_id: Uuid, side_cm: f64) -> ProductSpace {
+        ProductSpace {
+            product_id,
+            length_cm: Some(side_cm),
+            width_cm: Some(side_cm),
+            height_cm: Some(side_cm),
+            overrides: HashMap::new(),
+        }
+    }
+
+    fn capacity(location_id: Uuid, bin_code: Option<&str>, unit: CapacityUnit, capacity: f64) -> LocationCapacity {
+        LocationCapacity {
+            location_id,
+            bin_code: bin_code.map(str::to_string),
+            unit,
+            capacity,
+        }
+    }
+
+    fn stored(product_id: Uuid, bin_code: Option<&str>, quantity: i64) -> StoredQuantity {
+        StoredQuantity { product_id, bin_code: bin_code.map(str::to_string), quantity }
+    }
+
+    fn incoming(product_id: Uuid, quantity: i64) -> IncomingQuantity {
+        IncomingQuantity { product_id, bin_code: None, quantity }
+    }
+
+    #[test]
+    fn test_space_factor_prefers_override_then_dimensions() {
+        let settings = CapacitySettings::default();
+        let mut product = boxed(Uuid::new_v4(), 50.0);
+        let pallets = product.space_factor(CapacityUnit::PalletPositions, &settings);
+        assert!((pallets.per_unit - 0.125 / 1.5).abs() < 1e-9);
+        assert!(!pallets.estimated);
+        assert!((product.space_factor(CapacityUnit::CubicMeters, &settings).per_unit - 0.125).abs() < 1e-9);
+
+        product.overrides.insert(CapacityUnit::PalletPositions, 0.25);
+        assert_eq!(product.space_factor(CapacityUnit::PalletPositions, &settings).per_unit, 0.25);
+
+        let unknown = ProductSpace { product_id: Uuid::new_v4(), ..Default::default() };
+        let factor = unknown.space_factor(CapacityUnit::CubicMeters, &settings);
+        assert_eq!(factor, SpaceFactor { per_unit: 0.01, estimated: true });
+        assert!(!unknown.space_factor(CapacityUnit::Units, &settings).estimated);
+    }
+
+    #[test]
+    fn test_warns_from_threshold_and_blocks_above_capacity() {
+        let (location, product) = (Uuid::new_v4(), Uuid::new_v4());
+        let capacities = [capacity(location, None, CapacityUnit::Units, 100.0)];
+        let stock = [stored(product, None, 60)];
+        let settings = CapacitySettings::default();
+        let check = |quantity| assess(location, &capacities, &stock, &[incoming(product, quantity)], &HashMap::new(), &settings);
+
+        let below = check(20);
+        assert_eq!(below.usages[0].level, CapacityLevel::Ok);
+        assert!(enforce(&below, false, false).unwrap().is_empty());
+
+        let near = check(25);
+        assert_eq!(near.usages[0].level, CapacityLevel::Warning);
+        let warnings = enforce(&near, false, false).unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].code, WarningCode::LocationNearCapacity);
+
+        let full = check(40);
+        assert_eq!(full.usages[0].level, CapacityLevel::Warning, "exactly 100% is still allowed");
+
+        let over = check(41);
+        assert!(matches!(enforce(&over, false, true), Err(MasterDataError::LocationCapacityExceeded { .. })));
+        assert!(matches!(enforce(&over, true, false), Err(MasterDataError::PermissionDenied { .. })));
+        let overridden = enforce(&over, true, true).unwrap();
+        assert_eq!(overridden[0].code, WarningCode::CapacityOverridden);
+    }
+
+    #[test]
+    fn test_bin_capacity_counts_only_its_bin() {
+        let location = Uuid::new_v4();
+        let (in_bin, elsewhere) = (Uuid::new_v4(), Uuid::new_v4());
+        let capacities = [
+            capacity(location, None, CapacityUnit::Units, 1000.0),
+            capacity(location, Some("A-01"), CapacityUnit::Units, 10.0),
+        ];
+        let stock = [stored(in_bin, Some("A-01"), 8), stored(elsewhere, Some("B-02"), 500)];
+
+        let assessment = assess(
+            location,
+            &capacities,
+            &stock,
+            &[incoming(in_bin, 5)],
+            &HashMap::new(),
+            &CapacitySettings::default(),
+        );
+
+        let bin = assessment.usages.iter().find(|u| u.bin_code.as_deref() == Some("A-01")).unwrap();
+        assert_eq!((bin.occupied, bin.incoming, bin.level), (8.0, 5.0, CapacityLevel::Exceeded));
+        let whole = assessment.usages.iter().find(|u| u.bin_code.is_none()).unwrap();
+        assert_eq!((whole.occupied, whole.level), (508.0, CapacityLevel::Ok));
+    }
+
+    #[test]
+    fn test_products_without_dimensions_warn_when_estimated() {
+        let (location, product) = (Uuid::new_v4(), Uuid::new_v4());
+        let capacities = [capacity(location, None, CapacityUnit::CubicMeters, 10.0)];
+
+        let assessment = assess(location, &capacities, &[], &[incoming(product, 100)], &HashMap::new(), &CapacitySettings::default());
+
+        assert_eq!(assessment.estimated_products, vec![product]);
+        assert!((assessment.usages[0].incoming - 1.0).abs() < 1e-9);
+        let warnings = enforce(&assessment, false, false).unwrap();
+        assert_eq!(warnings[0].code, WarningCode::SpaceFactorDefaulted);
+
+        let unchecked = assess(location, &[], &[], &[incoming(product, 100)], &HashMap::new(), &CapacitySettings::default());
+        assert!(enforce(&unchecked, false, false).unwrap().is_empty(), "locations without capacity are not checked");
+    }
+
+    #[test]
+    fn test_units_that_fit_uses_the_tightest_capacity() {
+        let (location, product) = (Uuid::new_v4(), Uuid::new_v4());
+        let settings = CapacitySettings::default();
+        let space = boxed(product, 50.0);
+        let capacities = [
+            capacity(location, None, CapacityUnit::CubicMeters, 10.0),
+            capacity(location, None, CapacityUnit::Units, 100.0),
+        ];
+        let products = HashMap::from([(product, space.clone())]);
+
+        let assessment = assess(location, &capacities, &[stored(product, None, 60)], &[], &products, &settings);
+
+        // 2.5 m³ free fit 20 units, but only 40 more units fit at all
+        assert_eq!(units_that_fit(&assessment, &space, &settings), Some(20));
+        let unlimited = assess(location, &[], &[], &[], &products, &settings);
+        assert_eq!(units_that_fit(&unlimited, &space, &settings), None);
+    }
+
+    #[test]
+    fn test_rebalancing_proposals_share_the_free_capacity() {
+        let location = Uuid::new_v4();
+        let (first, second, third) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let capacities = [capacity(location, None, CapacityUnit::Units, 200.0)];
+        let stock = [stored(first, None, 120)];
+
+        let fitted = fit_into_free_capacity(
+            location,
+            &capacities,
+            &stock,
+            &[(first, 30), (second, 100), (third, 25)],
+            &HashMap::new(),
+            &CapacitySettings::default(),
+        );
+
+        assert_eq!(fitted, vec![30, 50, 0]);
+        let unlimited = fit_into_free_capacity(location, &[], &stock, &[(first, 30)], &HashMap::new(), &CapacitySettings::default());
+        assert_eq!(unlimited, vec![30]);
+    }
+}
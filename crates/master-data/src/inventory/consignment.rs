@@ -0,0 +1,546 @@
+//! # Consignment Stock
+//!
+//! Stock a supplier keeps in our locations stays theirs until we consume it.
+//! It counts in the location's quantities like any other unit, so it is
+//! available for sale and shows in availability with its consignment part;
+//! the supplier-owned part of each product and location is kept per supplier
+//! and left out of our valuation and KPIs (see
+//! [`own_stock`](super::valuation::own_stock)).
+//!
+//! - A receipt books units in as the supplier's; it has no valuation impact.
+//! - A transfer moves consignment units between locations and keeps them the
+//!   supplier's.
+//! - A consumption ends the supplier's ownership, either by selling the units
+//!   or by taking them over into our own stock. It is priced with the
+//!   supplier's catalog entry valid at that moment ([`consignment_price`]),
+//!   which makes it what we owe the supplier.
+//!
+//! [`settlement`] adds up a supplier's stock on hand and what was consumed in
+//! a period, the basis of the settlement with the supplier.
+
+use crate::error::{MasterDataError, Result};
+use crate::inventory::model::InventoryMovement;
+use crate::supplier::catalog::SupplierCatalogEntry;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConsignmentMovementKind {
+    Receipt,
+    TransferOut,
+    TransferIn,
+    Sale,
+    TransferToOwn,
+}
+
+impl ConsignmentMovementKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConsignmentMovementKind::Receipt => "receipt",
+            ConsignmentMovementKind::TransferOut => "transfer_out",
+            ConsignmentMovementKind::TransferIn => "transfer_in",
+            ConsignmentMovementKind::Sale => "sale",
+            ConsignmentMovementKind::TransferToOwn => "transfer_to_own",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "receipt" => Some(ConsignmentMovementKind::Receipt),
+            "transfer_out" => Some(ConsignmentMovementKind::TransferOut),
+            "transfer_in" => Some(ConsignmentMovementKind::TransferIn),
+            "sale" => Some(ConsignmentMovementKind::Sale),
+            "transfer_to_own" => Some(ConsignmentMovementKind::TransferToOwn),
+            _ => None,
+        }
+    }
+
+    /// Whether the movement ends the supplier's ownership and is owed to them
+    pub fn is_consumption(&self) -> bool {
+        matches!(self, ConsignmentMovementKind::Sale | ConsignmentMovementKind::TransferToOwn)
+    }
+
+    /// Change of the supplier's stock at the location by a movement of `quantity`
+    pub fn balance_change(&self, quantity: i32) -> i32 {
+        match self {
+            ConsignmentMovementKind::Receipt | ConsignmentMovementKind::TransferIn => quantity,
+            _ => -quantity,
+        }
+    }
+}
+
+/// How consigned units are consumed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConsumptionKind {
+    /// Sold out of the location
+    Sale,
+    /// Taken over into our own stock; the units stay where they are
+    TransferToOwn,
+}
+
+impl From<ConsumptionKind> for ConsignmentMovementKind {
+    fn from(kind: ConsumptionKind) -> Self {
+        match kind {
+            ConsumptionKind::Sale => ConsignmentMovementKind::Sale,
+            ConsumptionKind::TransferToOwn => ConsignmentMovementKind::TransferToOwn,
+        }
+    }
+}
+
+/// A change of a supplier's stock at one location
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConsignmentMovement {
+    pub id: Uuid,
+    pub supplier_id: Uuid,
+    pub product_id: Uuid,
+    pub location_id: Uuid,
+    pub movement: ConsignmentMovementKind,
+    pub quantity: i32,
+    /// Consignment price of consumptions, minor units of `currency`
+    pub unit_price: Option<i64>,
+    pub currency: Option<String>,
+    pub catalog_entry_id: Option<Uuid>,
+    pub reference: Option<String>,
+    pub created_by: Uuid,
+    pub created_at: DateTime<Utc>,
+}
+
+impl ConsignmentMovement {
+    /// What a consumption owes the supplier, in minor units of `currency`
+    pub fn amount(&self) -> Option<i64> {
+        self.unit_price.map(|price| price.saturating_mul(self.quantity as i64))
+    }
+}
+
+/// A supplier's stock of a product at a location
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConsignmentBalance {
+    pub supplier_id: Uuid,
+    pub product_id: Uuid,
+    pub location_id: Uuid,
+    pub quantity: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConsignmentReceiptRequest {
+    pub supplier_id: Uuid,
+    pub product_id: Uuid,
+    pub location_id: Uuid,
+    pub quantity: i32,
+    /// Delivery note or similar
+    pub reference: Option<String>,
+    /// Store the units even if the location ends up over capacity;
+    /// requires `inventory:capacity_override`
+    #[serde(default)]
+    pub override_capacity: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConsignmentConsumptionRequest {
+    pub supplier_id: Uuid,
+    pub product_id: Uuid,
+    pub location_id: Uuid,
+    pub quantity: i32,
+    pub kind: ConsumptionKind,
+    /// Sales order or similar
+    pub reference: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConsignmentTransferRequest {
+    pub supplier_id: Uuid,
+    pub product_id: Uuid,
+    pub from_location_id: Uuid,
+    pub to_location_id: Uuid,
+    pub quantity: i32,
+    pub reference: Option<String>,
+    /// Store the units even if the target location ends up over capacity;
+    /// requires `inventory:capacity_override`
+    #[serde(default)]
+    pub override_capacity: bool,
+}
+
+/// The supplier's price for consumed units
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConsignmentPrice {
+    pub catalog_entry_id: Uuid,
+    pub unit_price: i64,
+    pub currency: String,
+}
+
+fn validate_quantity(quantity: i32) -> Result<()> {
+    if quantity <= 0 {
+        return Err(MasterDataError::ValidationError {
+            field: "quantity".to_string(),
+            message: "Quantity must be positive".to_string(),
+        });
+    }
+    Ok(())
+}
+
+fn check_balance(balance: i32, quantity: i32) -> Result<()> {
+    if quantity > balance {
+        return Err(MasterDataError::ValidationError {
+            field: "quantity".to_string(),
+            message: format!("Only {} unit(s) of the supplier's consignment stock are at the location", balance),
+        });
+    }
+    Ok(())
+}
+
+/// Price of `quantity` consumed units at `at`: the price break reached in the
+/// supplier's catalog entry valid then, the most recently started one if
+/// several are
+pub fn consignment_price(entries: &[SupplierCatalogEntry], quantity: i32, at: DateTime<Utc>) -> Option<ConsignmentPrice> {
+    let entry = entries
+        .iter()
+        .filter(|entry| entry.is_valid_at(at))
+        .max_by_key(|entry| entry.valid_from)?;
+    Some(ConsignmentPrice {
+        catalog_entry_id: entry.id,
+        unit_price: entry.unit_price_for(quantity)?,
+        currency: entry.currency.clone(),
+    })
+}
+
+/// Units booked in as the supplier's
+pub fn receipt(request: &ConsignmentReceiptRequest, operator: Uuid, now: DateTime<Utc>) -> Result<ConsignmentMovement> {
+    validate_quantity(request.quantity)?;
+    Ok(ConsignmentMovement {
+        id: Uuid::new_v4(),
+        supplier_id: request.supplier_id,
+        product_id: request.product_id,
+        location_id: request.location_id,
+        movement: ConsignmentMovementKind::Receipt,
+        quantity: request.quantity,
+        unit_price: None,
+        currency: None,
+        catalog_entry_id: None,
+        reference: request.reference.clone(),
+        created_by: operator,
+        created_at: now,
+    })
+}
+
+/// Consumption of up to `balance` units the supplier has at the location, at `price`
+pub fn consumption(
+    request: &ConsignmentConsumptionRequest,
+    balance: i32,
+    price: &ConsignmentPrice,
+    operator: Uuid,
+    now: DateTime<Utc>,
+) -> Result<ConsignmentMovement> {
+    validate_quantity(request.quantity)?;
+    check_balance(balance, request.quantity)?;
+    Ok(ConsignmentMovement {
+        id: Uuid::new_v4(),
+        supplier_id: request.supplier_id,
+        product_id: request.product_id,
+        location_id: request.location_id,
+        movement: request.kind.into(),
+        quantity: request.quantity,
+        unit_price: Some(price.unit_price),
+        currency: Some(price.currency.clone()),
+        catalog_entry_id: Some(price.catalog_entry_id),
+        reference: request.reference.clone(),
+        created_by: operator,
+        created_at: now,
+    })
+}
+
+/// The two sides of moving up to `balance` units of the supplier to another location
+pub fn transfer(
+    request: &ConsignmentTransferRequest,
+    balance: i32,
+    operator: Uuid,
+    now: DateTime<Utc>,
+) -> Result<[ConsignmentMovement; 2]> {
+    validate_quantity(request.quantity)?;
+    if request.from_location_id == request.to_location_id {
+        return Err(MasterDataError::ValidationError {
+            field: "to_location_id".to_string(),
+            message: "Cannot transfer to the same location".to_string(),
+        });
+    }
+    check_balance(balance, request.quantity)?;
+
+    let side = |location_id: Uuid, movement: ConsignmentMovementKind| ConsignmentMovement {
+        id: Uuid::new_v4(),
+        supplier_id: request.supplier_id,
+        product_id: request.product_id,
+        location_id,
+        movement,
+        quantity: request.quantity,
+        unit_price: None,
+        currency: None,
+        catalog_entry_id: None,
+        reference: request.reference.clone(),
+        created_by: operator,
+        created_at: now,
+    };
+    Ok([
+        side(request.from_location_id, ConsignmentMovementKind::TransferOut),
+        side(request.to_location_id, ConsignmentMovementKind::TransferIn),
+    ])
+}
+
+/// The stock movement of the location a consignment movement makes, if any;
+/// units taken over into our own stock do not move
+pub fn stock_movement(movement: &ConsignmentMovement) -> Option<InventoryMovement> {
+    let movement_type = match movement.movement {
+        ConsignmentMovementKind::Receipt => "receipt",
+        ConsignmentMovementKind::TransferOut | ConsignmentMovementKind::TransferIn => "transfer",
+        ConsignmentMovementKind::Sale => "shipment",
+        ConsignmentMovementKind::TransferToOwn => return None,
+    };
+    Some(InventoryMovement {
+        id: Some(Uuid::new_v4()),
+        product_id: Some(movement.product_id),
+        location_id: Some(movement.location_id),
+        movement_type: Some(movement_type.to_string()),
+        quantity: Some(movement.movement.balance_change(movement.quantity)),
+        unit_cost: None,
+        reference_document: Some("consignment".to_string()),
+        reference_number: movement.reference.clone(),
+        reason: Some(format!("consignment_{}", movement.movement.as_str())),
+        batch_number: None,
+        serial_numbers: None,
+        expiry_date: None,
+        operator_id: Some(movement.created_by),
+        operator_name: None,
+        created_at: Some(movement.created_at),
+        effective_date: Some(movement.created_at),
+        audit_trail: None,
+    })
+}
+
+/// Consumption of one product within the period
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConsumedProduct {
+    pub product_id: Uuid,
+    pub currency: String,
+    pub sold: i64,
+    pub transferred_to_own: i64,
+    /// Owed to the supplier, minor units of `currency`
+    pub amount: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConsignmentSettlement {
+    pub supplier_id: Uuid,
+    pub from: NaiveDate,
+    /// Inclusive
+    pub to: NaiveDate,
+    /// The supplier's stock now, per product and location
+    pub on_hand: Vec<ConsignmentBalance>,
+    pub on_hand_units: i64,
+    pub received_units: i64,
+    pub consumed: Vec<ConsumedProduct>,
+    /// Owed per currency
+    pub totals: BTreeMap<String, i64>,
+}
+
+/// A supplier's stock on hand and what was consumed of it from `from` to `to`
+pub fn settlement(
+    supplier_id: Uuid,
+    from: NaiveDate,
+    to: NaiveDate,
+    balances: &[ConsignmentBalance],
+    movements: &[ConsignmentMovement],
+) -> ConsignmentSettlement {
+    let on_hand: Vec<ConsignmentBalance> = balances
+        .iter()
+        .filter(|balance| balance.supplier_id == supplier_id && balance.quantity > 0)
+        .cloned()
+        .collect();
+    let in_period: Vec<&ConsignmentMovement> = movements
+        .iter()
+        .filter(|m| m.supplier_id == supplier_id)
+        .filter(|m| (from..=to).contains(&m.created_at.date_naive()))
+        .collect();
+
+    let mut consumed: BTreeMap<(Uuid, String), ConsumedProduct> = BTreeMap::new();
+    let mut totals: BTreeMap<String, i64> = BTreeMap::new();
+    for movement in in_period.iter().filter(|m| m.movement.is_consumption()) {
+        let currency = movement.currency.clone().unwrap_or_default();
+        let amount = movement.amount().unwrap_or(0);
+        let line = consumed
+            .entry((movement.product_id, currency.clone()))
+            .or_insert_with(|| ConsumedProduct {
+                product_id: movement.product_id,
+                currency: currency.clone(),
+                sold: 0,
+                transferred_to_own: 0,
+                amount: 0,
+            });
+        match movement.movement {
+            ConsignmentMovementKind::Sale => line.sold += movement.quantity as i64,
+            _ => line.transferred_to_own += movement.quantity as i64,
+        }
+        line.amount = line.amount.saturating_add(amount);
+        *totals.entry(currency).or_insert(0) += amount;
+    }
+
+    ConsignmentSettlement {
+        supplier_id,
+        from,
+        to,
+        on_hand_units: on_hand.iter().map(|balance| balance.quantity as i64).sum(),
+        on_hand,
+        received_units: in_period
+            .iter()
+            .filter(|m| m.movement == ConsignmentMovementKind::Receipt)
+            .map(|m| m.quantity as i64)
+            .sum(),
+        consumed: consumed.into_values().collect(),
+        totals,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::supplier::catalog::PriceBreak;
+    use chrono::{Duration, TimeZone};
+
+    fn at(day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 3, day, 12, 0, 0).unwrap()
+    }
+
+    fn entry(supplier_id: Uuid, product_id: Uuid, valid_from: DateTime<Utc>, breaks: &[(i32, i64)]) -> SupplierCatalogEntry {
+        SupplierCatalogEntry {
+            id: Uuid::new_v4(),
+            tenant_id: Uuid::new_v4(),
+            supplier_id,
+            product_id,
+            supplier_sku: None,
+            currency: "EUR".to_string(),
+            price_breaks: breaks
+                .iter()
+                .map(|&(min_quantity, unit_price)| PriceBreak { min_quantity, unit_price })
+                .collect(),
+            min_order_quantity: 1,
+            pack_size: 1,
+            lead_time_days: 0,
+            valid_from,
+            valid_to: None,
+            is_active: true,
+            created_at: valid_from,
+            updated_at: valid_from,
+            created_by: Uuid::nil(),
+            updated_by: Uuid::nil(),
+        }
+    }
+
+    fn consume(supplier_id: Uuid, product_id: Uuid, quantity: i32, kind: ConsumptionKind) -> ConsignmentConsumptionRequest {
+        ConsignmentConsumptionRequest {
+            supplier_id,
+            product_id,
+            location_id: Uuid::new_v4(),
+            quantity,
+            kind,
+            reference: None,
+        }
+    }
+
+    #[test]
+    fn test_consumption_transfers_ownership_at_the_catalog_price() {
+        let (supplier, product) = (Uuid::new_v4(), Uuid::new_v4());
+        let entries = vec![
+            entry(supplier, product, at(1) - Duration::days(30), &[(1, 1500)]),
+            entry(supplier, product, at(1), &[(1, 1200), (10, 1000)]),
+        ];
+        let price = consignment_price(&entries, 12, at(2)).unwrap();
+        assert_eq!(price.catalog_entry_id, entries[1].id);
+        assert_eq!(price.unit_price, 1000);
+
+        let request = consume(supplier, product, 12, ConsumptionKind::TransferToOwn);
+        let movement = consumption(&request, 20, &price, Uuid::nil(), at(2)).unwrap();
+        assert_eq!(movement.movement, ConsignmentMovementKind::TransferToOwn);
+        assert_eq!(movement.amount(), Some(12_000));
+        assert_eq!(movement.movement.balance_change(movement.quantity), -12);
+        // The units stay where they are, now as our own stock
+        assert!(stock_movement(&movement).is_none());
+
+        let sale = consumption(&consume(supplier, product, 3, ConsumptionKind::Sale), 20, &price, Uuid::nil(), at(2)).unwrap();
+        let shipped = stock_movement(&sale).unwrap();
+        assert_eq!(shipped.movement_type.as_deref(), Some("shipment"));
+        assert_eq!(shipped.quantity, Some(-3));
+
+        // No more than the supplier has at the location, and only with a valid price
+        assert!(consumption(&request, 11, &price, Uuid::nil(), at(2)).is_err());
+        assert!(consignment_price(&entries, 1, at(1) - Duration::days(60)).is_none());
+    }
+
+    #[test]
+    fn test_transfer_keeps_the_units_the_suppliers() {
+        let request = ConsignmentTransferRequest {
+            supplier_id: Uuid::new_v4(),
+            product_id: Uuid::new_v4(),
+            from_location_id: Uuid::new_v4(),
+            to_location_id: Uuid::new_v4(),
+            quantity: 4,
+            reference: None,
+            override_capacity: false,
+        };
+        let [out, into] = transfer(&request, 4, Uuid::nil(), at(3)).unwrap();
+        assert_eq!((out.location_id, out.movement.balance_change(out.quantity)), (request.from_location_id, -4));
+        assert_eq!((into.location_id, into.movement.balance_change(into.quantity)), (request.to_location_id, 4));
+        assert!(out.supplier_id == request.supplier_id && into.supplier_id == request.supplier_id);
+        assert_eq!(stock_movement(&into).unwrap().quantity, Some(4));
+
+        assert!(transfer(&request, 3, Uuid::nil(), at(3)).is_err());
+        let same = ConsignmentTransferRequest { to_location_id: request.from_location_id, ..request };
+        assert!(transfer(&same, 4, Uuid::nil(), at(3)).is_err());
+    }
+
+    #[test]
+    fn test_settlement_adds_up_the_period() {
+        let (supplier, other) = (Uuid::new_v4(), Uuid::new_v4());
+        let (product, location) = (Uuid::new_v4(), Uuid::new_v4());
+        let price = ConsignmentPrice {
+            catalog_entry_id: Uuid::new_v4(),
+            unit_price: 250,
+            currency: "EUR".to_string(),
+        };
+        let received = receipt(
+            &ConsignmentReceiptRequest {
+                supplier_id: supplier,
+                product_id: product,
+                location_id: location,
+                quantity: 40,
+                reference: None,
+                override_capacity: false,
+            },
+            Uuid::nil(),
+            at(2),
+        )
+        .unwrap();
+        let movements = vec![
+            received,
+            consumption(&consume(supplier, product, 5, ConsumptionKind::Sale), 40, &price, Uuid::nil(), at(10)).unwrap(),
+            consumption(&consume(supplier, product, 3, ConsumptionKind::TransferToOwn), 35, &price, Uuid::nil(), at(20)).unwrap(),
+            // Before the period
+            consumption(&consume(supplier, product, 7, ConsumptionKind::Sale), 40, &price, Uuid::nil(), at(1)).unwrap(),
+            // Another supplier's
+            consumption(&consume(other, product, 9, ConsumptionKind::Sale), 40, &price, Uuid::nil(), at(10)).unwrap(),
+        ];
+        let balances = vec![
+            ConsignmentBalance { supplier_id: supplier, product_id: product, location_id: location, quantity: 25 },
+            ConsignmentBalance { supplier_id: other, product_id: product, location_id: location, quantity: 9 },
+        ];
+
+        let report = settlement(supplier, at(2).date_naive(), at(31).date_naive(), &balances, &movements);
+        assert_eq!(report.on_hand_units, 25);
+        assert_eq!(report.on_hand.len(), 1);
+        assert_eq!(report.received_units, 40);
+        assert_eq!(report.consumed.len(), 1);
+        assert_eq!((report.consumed[0].sold, report.consumed[0].transferred_to_own), (5, 3));
+        assert_eq!(report.consumed[0].amount, 8 * 250);
+        assert_eq!(report.totals.get("EUR"), Some(&2000));
+    }
+}
@@ -0,0 +1,584 @@
+//! # Inbound Movement Costing
+//!
+//! Every movement bringing stock in carries a unit cost, also when the sender
+//! (a WMS, a scanner) leaves it out. A missing cost is taken from the first
+//! source of the tenant's cost source chain that has one; by default the
+//! price of the purchase order line the movement receives, the supplier
+//! catalog price for the quantity, the product's cost price, and the cost of
+//! the product's last receipt. The source is stored with the movement, and a
+//! cost that only the last, weakest source of the chain supplied is flagged
+//! for finance to review. Tenants with strict costing reject an inbound
+//! movement whose cost can't be resolved. Consignment stock stays the
+//! supplier's and is not costed.
+//!
+//! Costs are in the product's currency, in major units like
+//! `inventory_transactions.unit_cost`; catalog prices and cost prices are
+//! stored in cents and converted. Catalog entries in another currency are
+//! not used.
+//!
+//! A wrong cost is restated, never overwritten: the movement is reversed at
+//! its old cost and booked again at the new one, both movements of their
+//! own that leave the quantity on hand alone. The cost layers, the inbound
+//! movements still carrying their cost, then value the stock at the new cost.
+//!
+//! The functions here are pure; costs are resolved as movements are stored
+//! in the [`repository`](super::repository), and settings, reports and
+//! restatements go through
+//! [`InventoryCostingService`](super::service::InventoryCostingService).
+
+use crate::error::{MasterDataError, Result};
+use crate::inventory::model::InventoryMovement;
+use chrono::{DateTime, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use uuid::Uuid;
+
+/// Where the unit cost of a movement came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CostSource {
+    /// Sent with the movement
+    Provided,
+    PurchaseOrderLine,
+    SupplierCatalog,
+    ProductCostPrice,
+    LastReceipt,
+    /// Booked again at a restated cost
+    Restated,
+}
+
+impl CostSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CostSource::Provided => "provided",
+            CostSource::PurchaseOrderLine => "purchase_order_line",
+            CostSource::SupplierCatalog => "supplier_catalog",
+            CostSource::ProductCostPrice => "product_cost_price",
+            CostSource::LastReceipt => "last_receipt",
+            CostSource::Restated => "restated",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "provided" => Some(CostSource::Provided),
+            "purchase_order_line" => Some(CostSource::PurchaseOrderLine),
+            "supplier_catalog" => Some(CostSource::SupplierCatalog),
+            "product_cost_price" => Some(CostSource::ProductCostPrice),
+            "last_receipt" => Some(CostSource::LastReceipt),
+            "restated" => Some(CostSource::Restated),
+            _ => None,
+        }
+    }
+
+    /// Whether the source can stand in for a missing cost
+    pub fn is_fallback(&self) -> bool {
+        matches!(
+            self,
+            CostSource::PurchaseOrderLine | CostSource::SupplierCatalog | CostSource::ProductCostPrice | CostSource::LastReceipt
+        )
+    }
+}
+
+pub const DEFAULT_COST_SOURCE_CHAIN: [CostSource; 4] = [
+    CostSource::PurchaseOrderLine,
+    CostSource::SupplierCatalog,
+    CostSource::ProductCostPrice,
+    CostSource::LastReceipt,
+];
+
+/// How a tenant costs inbound movements that come without a cost
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CostingSettings {
+    /// Tried in order; a cost from the last one alone is flagged for review
+    pub cost_source_chain: Vec<CostSource>,
+    /// Reject inbound movements whose cost none of the sources has
+    pub strict_costing: bool,
+}
+
+impl Default for CostingSettings {
+    fn default() -> Self {
+        Self {
+            cost_source_chain: DEFAULT_COST_SOURCE_CHAIN.to_vec(),
+            strict_costing: false,
+        }
+    }
+}
+
+impl CostingSettings {
+    pub fn validate(&self) -> Result<()> {
+        let invalid = |message: String| MasterDataError::ValidationError {
+            field: "cost_source_chain".to_string(),
+            message,
+        };
+        if self.cost_source_chain.is_empty() {
+            return Err(invalid("The cost source chain needs at least one source".to_string()));
+        }
+        let mut seen = HashSet::new();
+        for source in &self.cost_source_chain {
+            if !source.is_fallback() {
+                return Err(invalid(format!("{} can't resolve a missing cost", source.as_str())));
+            }
+            if !seen.insert(source) {
+                return Err(invalid(format!("{} is in the chain twice", source.as_str())));
+            }
+        }
+        Ok(())
+    }
+
+    /// The source whose cost alone gets a movement flagged
+    pub fn weakest_source(&self) -> Option<CostSource> {
+        self.cost_source_chain.last().copied()
+    }
+}
+
+/// What each source would give as the unit cost of a movement
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CostCandidates {
+    pub purchase_order_line: Option<Decimal>,
+    pub supplier_catalog: Option<Decimal>,
+    pub product_cost_price: Option<Decimal>,
+    pub last_receipt: Option<Decimal>,
+}
+
+impl CostCandidates {
+    pub fn get(&self, source: CostSource) -> Option<Decimal> {
+        match source {
+            CostSource::PurchaseOrderLine => self.purchase_order_line,
+            CostSource::SupplierCatalog => self.supplier_catalog,
+            CostSource::ProductCostPrice => self.product_cost_price,
+            CostSource::LastReceipt => self.last_receipt,
+            CostSource::Provided | CostSource::Restated => None,
+        }
+    }
+}
+
+/// Major units of an amount in cents
+pub fn from_cents(cents: i64) -> Decimal {
+    Decimal::new(cents, 2)
+}
+
+/// The unit cost a movement is stored with
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct ResolvedCost {
+    pub unit_cost: Decimal,
+    pub source: CostSource,
+    pub needs_review: bool,
+}
+
+impl ResolvedCost {
+    pub fn provided(unit_cost: Decimal) -> Self {
+        Self {
+            unit_cost,
+            source: CostSource::Provided,
+            needs_review: false,
+        }
+    }
+}
+
+/// Whether the movement brings our own stock in and so needs a cost
+pub fn needs_cost(movement: &InventoryMovement) -> bool {
+    movement.quantity.unwrap_or(0) > 0 && movement.reference_document.as_deref() != Some("consignment")
+}
+
+/// The cost an inbound movement of `product_id` is stored with: the cost it
+/// came with, else the first source of the chain that has one. `None` when
+/// no source has one, an error instead under strict costing.
+pub fn resolve_unit_cost(
+    provided: Option<Decimal>,
+    candidates: &CostCandidates,
+    settings: &CostingSettings,
+    product_id: Uuid,
+) -> Result<Option<ResolvedCost>> {
+    if let Some(unit_cost) = provided {
+        return Ok(Some(ResolvedCost::provided(unit_cost)));
+    }
+
+    let resolved = settings.cost_source_chain.iter().find_map(|&source| {
+        candidates.get(source).map(|unit_cost| ResolvedCost {
+            unit_cost,
+            source,
+            needs_review: settings.weakest_source() == Some(source),
+        })
+    });
+    if resolved.is_none() && settings.strict_costing {
+        let chain: Vec<&str> = settings.cost_source_chain.iter().map(CostSource::as_str).collect();
+        return Err(MasterDataError::ValidationError {
+            field: "unit_cost".to_string(),
+            message: format!(
+                "No unit cost for product {}: none was given and none of {} has one",
+                product_id,
+                chain.join(", ")
+            ),
+        });
+    }
+    Ok(resolved)
+}
+
+/// A stored movement with its cost
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CostedMovement {
+    pub id: Uuid,
+    pub product_id: Uuid,
+    pub location_id: Uuid,
+    pub movement_type: String,
+    pub quantity: i32,
+    pub unit_cost: Option<Decimal>,
+    pub cost_source: Option<CostSource>,
+    pub cost_needs_review: bool,
+    /// Set on the movement reversing this one
+    pub reversal_of: Option<Uuid>,
+    /// Set on the movement booking this one again at a restated cost
+    pub restatement_of: Option<Uuid>,
+    pub reference_document: Option<String>,
+    pub reference_number: Option<String>,
+    pub reason: Option<String>,
+    pub occurred_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CostRestatementRequest {
+    pub unit_cost: Decimal,
+    pub reason: String,
+}
+
+/// The movements restating the cost of an inbound movement
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CostRestatement {
+    pub movement_id: Uuid,
+    /// Takes the movement out at its old cost
+    pub reversal: CostedMovement,
+    /// Brings it in again at the new cost
+    pub rebooking: CostedMovement,
+    /// Change of the stock value at cost
+    pub value_change: Decimal,
+}
+
+/// Restate the cost of `original`; `reversed` tells whether it was reversed already
+pub fn restatement(
+    original: &CostedMovement,
+    reversed: bool,
+    request: &CostRestatementRequest,
+    now: DateTime<Utc>,
+) -> Result<CostRestatement> {
+    let invalid = |field: &str, message: String| MasterDataError::ValidationError {
+        field: field.to_string(),
+        message,
+    };
+    if original.quantity <= 0 || original.reversal_of.is_some() {
+        return Err(invalid("movement_id", format!("Movement {} brings no stock in", original.id)));
+    }
+    if reversed {
+        return Err(invalid(
+            "movement_id",
+            format!("Movement {} was restated already; restate its re-booking instead", original.id),
+        ));
+    }
+    let Some(old_cost) = original.unit_cost else {
+        return Err(invalid("movement_id", format!("Movement {} has no cost to restate", original.id)));
+    };
+    if request.unit_cost < Decimal::ZERO {
+        return Err(invalid("unit_cost", "The unit cost can't be negative".to_string()));
+    }
+    if request.unit_cost == old_cost {
+        return Err(invalid("unit_cost", format!("Movement {} already costs {}", original.id, old_cost)));
+    }
+    if request.reason.trim().is_empty() {
+        return Err(invalid("reason", "A restatement needs a reason".to_string()));
+    }
+
+    let reason = Some(format!("cost restatement: {}", request.reason.trim()));
+    let reversal = CostedMovement {
+        id: Uuid::new_v4(),
+        quantity: -original.quantity,
+        reversal_of: Some(original.id),
+        restatement_of: None,
+        reason: reason.clone(),
+        occurred_at: now,
+        ..original.clone()
+    };
+    let rebooking = CostedMovement {
+        id: Uuid::new_v4(),
+        unit_cost: Some(request.unit_cost),
+        cost_source: Some(CostSource::Restated),
+        cost_needs_review: false,
+        reversal_of: None,
+        restatement_of: Some(original.id),
+        reason,
+        occurred_at: now,
+        ..original.clone()
+    };
+    Ok(CostRestatement {
+        movement_id: original.id,
+        value_change: (request.unit_cost - old_cost) * Decimal::from(original.quantity),
+        reversal,
+        rebooking,
+    })
+}
+
+/// Units of an inbound movement that still carry its cost
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CostLayer {
+    pub movement_id: Uuid,
+    pub product_id: Uuid,
+    pub location_id: Uuid,
+    pub quantity: i32,
+    pub unit_cost: Decimal,
+    pub source: CostSource,
+    pub needs_review: bool,
+    pub received_at: DateTime<Utc>,
+}
+
+impl CostLayer {
+    pub fn value(&self) -> Decimal {
+        self.unit_cost * Decimal::from(self.quantity)
+    }
+}
+
+/// The costed inbound movements that no reversal among `movements` took out
+pub fn cost_layers(movements: &[CostedMovement]) -> Vec<CostLayer> {
+    let reversed: HashSet<Uuid> = movements.iter().filter_map(|m| m.reversal_of).collect();
+    movements
+        .iter()
+        .filter(|m| m.quantity > 0 && m.reversal_of.is_none() && !reversed.contains(&m.id))
+        .filter_map(|m| {
+            Some(CostLayer {
+                movement_id: m.id,
+                product_id: m.product_id,
+                location_id: m.location_id,
+                quantity: m.quantity,
+                unit_cost: m.unit_cost?,
+                source: m.cost_source.unwrap_or(CostSource::Provided),
+                needs_review: m.cost_needs_review,
+                received_at: m.occurred_at,
+            })
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CostSourceSummary {
+    pub source: CostSource,
+    pub movements: usize,
+    pub quantity: i64,
+    /// At cost, in the products' currencies
+    pub value: Decimal,
+    pub needs_review: usize,
+}
+
+/// Inbound movements of a period by where their cost came from
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CostSourceReport {
+    pub from: NaiveDate,
+    /// Inclusive
+    pub to: NaiveDate,
+    /// Over the cost layers: restated movements count at their new cost
+    pub by_source: Vec<CostSourceSummary>,
+    /// Inbound movements of the period without any cost
+    pub uncosted: usize,
+    /// Layers whose cost only the weakest source supplied
+    pub needs_review: Vec<CostLayer>,
+    /// Every inbound movement of the period, restated ones and re-bookings included
+    pub movements: Vec<CostedMovement>,
+}
+
+/// Report on `movements`, those of `from` to `to`. A restatement counts when
+/// it was made: a receipt reversed in the period no longer counts at its old
+/// cost, one reversed after it still does.
+pub fn cost_source_report(movements: &[CostedMovement], from: NaiveDate, to: NaiveDate) -> CostSourceReport {
+    let in_period = |m: &&CostedMovement| {
+        let day = m.occurred_at.date_naive();
+        from <= day && day <= to
+    };
+    let layers: Vec<CostLayer> = cost_layers(movements)
+        .into_iter()
+        .filter(|layer| {
+            let day = layer.received_at.date_naive();
+            from <= day && day <= to
+        })
+        .collect();
+
+    let mut by_source: BTreeMap<CostSource, CostSourceSummary> = BTreeMap::new();
+    for layer in &layers {
+        let summary = by_source.entry(layer.source).or_insert_with(|| CostSourceSummary {
+            source: layer.source,
+            movements: 0,
+            quantity: 0,
+            value: Decimal::ZERO,
+            needs_review: 0,
+        });
+        summary.movements += 1;
+        summary.quantity += layer.quantity as i64;
+        summary.value += layer.value();
+        summary.needs_review += layer.needs_review as usize;
+    }
+
+    let inbound: Vec<CostedMovement> = movements
+        .iter()
+        .filter(in_period)
+        .filter(|m| m.quantity > 0 && m.reversal_of.is_none())
+        .cloned()
+        .collect();
+    CostSourceReport {
+        from,
+        to,
+        by_source: by_source.into_values().collect(),
+        uncosted: inbound.iter().filter(|m| m.unit_cost.is_none()).count(),
+        needs_review: layers.into_iter().filter(|layer| layer.needs_review).collect(),
+        movements: inbound,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dec(value: &str) -> Decimal {
+        value.parse().unwrap()
+    }
+
+    fn candidates() -> CostCandidates {
+        CostCandidates {
+            purchase_order_line: Some(dec("12.50")),
+            supplier_catalog: Some(dec("12.00")),
+            product_cost_price: Some(dec("11.00")),
+            last_receipt: Some(dec("10.00")),
+        }
+    }
+
+    fn resolve(candidates: &CostCandidates, settings: &CostingSettings) -> Option<ResolvedCost> {
+        resolve_unit_cost(None, candidates, settings, Uuid::new_v4()).unwrap()
+    }
+
+    #[test]
+    fn test_each_fallback_tier() {
+        let settings = CostingSettings::default();
+        let mut candidates = candidates();
+
+        let resolved = resolve_unit_cost(Some(dec("9.99")), &candidates, &settings, Uuid::new_v4()).unwrap().unwrap();
+        assert_eq!(resolved, ResolvedCost::provided(dec("9.99")));
+
+        let expected = [
+            (CostSource::PurchaseOrderLine, dec("12.50")),
+            (CostSource::SupplierCatalog, dec("12.00")),
+            (CostSource::ProductCostPrice, dec("11.00")),
+            (CostSource::LastReceipt, dec("10.00")),
+        ];
+        for (source, unit_cost) in expected {
+            let resolved = resolve(&candidates, &settings).unwrap();
+            assert_eq!((resolved.source, resolved.unit_cost), (source, unit_cost));
+            // Only the weakest source gets the movement flagged
+            assert_eq!(resolved.needs_review, source == CostSource::LastReceipt);
+            match source {
+                CostSource::PurchaseOrderLine => candidates.purchase_order_line = None,
+                CostSource::SupplierCatalog => candidates.supplier_catalog = None,
+                CostSource::ProductCostPrice => candidates.product_cost_price = None,
+                _ => candidates.last_receipt = None,
+            }
+        }
+        assert_eq!(resolve(&candidates, &settings), None);
+
+        // The chain decides the order and what is weakest
+        let settings = CostingSettings {
+            cost_source_chain: vec![CostSource::ProductCostPrice, CostSource::SupplierCatalog],
+            strict_costing: false,
+        };
+        let resolved = resolve(&self::candidates(), &settings).unwrap();
+        assert_eq!((resolved.source, resolved.needs_review), (CostSource::ProductCostPrice, false));
+        let only_catalog = CostCandidates {
+            supplier_catalog: Some(dec("12.00")),
+            ..CostCandidates::default()
+        };
+        assert!(resolve(&only_catalog, &settings).unwrap().needs_review);
+    }
+
+    #[test]
+    fn test_strict_costing_rejects_uncosted_receipts() {
+        let strict = CostingSettings {
+            strict_costing: true,
+            ..CostingSettings::default()
+        };
+        let error = resolve_unit_cost(None, &CostCandidates::default(), &strict, Uuid::new_v4()).unwrap_err();
+        assert!(matches!(error, MasterDataError::ValidationError { ref field, .. } if field == "unit_cost"));
+
+        // Any source will do
+        let last_receipt = CostCandidates {
+            last_receipt: Some(dec("4.20")),
+            ..CostCandidates::default()
+        };
+        assert!(resolve_unit_cost(None, &last_receipt, &strict, Uuid::new_v4()).unwrap().is_some());
+
+        assert!(CostingSettings { cost_source_chain: vec![], strict_costing: true }.validate().is_err());
+        assert!(CostingSettings {
+            cost_source_chain: vec![CostSource::LastReceipt, CostSource::Restated],
+            strict_costing: false
+        }
+        .validate()
+        .is_err());
+        assert!(CostingSettings {
+            cost_source_chain: vec![CostSource::LastReceipt, CostSource::LastReceipt],
+            strict_costing: false
+        }
+        .validate()
+        .is_err());
+    }
+
+    fn receipt(quantity: i32, unit_cost: Decimal, source: CostSource, at: DateTime<Utc>) -> CostedMovement {
+        CostedMovement {
+            id: Uuid::new_v4(),
+            product_id: Uuid::new_v4(),
+            location_id: Uuid::new_v4(),
+            movement_type: "receipt".to_string(),
+            quantity,
+            unit_cost: Some(unit_cost),
+            cost_source: Some(source),
+            cost_needs_review: source == CostSource::LastReceipt,
+            reversal_of: None,
+            restatement_of: None,
+            reference_document: None,
+            reference_number: None,
+            reason: None,
+            occurred_at: at,
+        }
+    }
+
+    #[test]
+    fn test_restatement_revalues_the_cost_layers() {
+        let received = "2025-03-03T10:00:00Z".parse().unwrap();
+        let flagged = receipt(10, dec("5.00"), CostSource::LastReceipt, received);
+        let other = receipt(4, dec("2.50"), CostSource::Provided, received);
+        let mut movements = vec![flagged.clone(), other];
+        let value = |movements: &[CostedMovement]| cost_layers(movements).iter().map(CostLayer::value).sum::<Decimal>();
+        assert_eq!(value(&movements), dec("60.00"));
+
+        let request = CostRestatementRequest {
+            unit_cost: dec("7.00"),
+            reason: "supplier invoice".to_string(),
+        };
+        let restated = restatement(&flagged, false, &request, "2025-04-01T08:00:00Z".parse().unwrap()).unwrap();
+        assert_eq!(restated.value_change, dec("20.00"));
+        assert_eq!(restated.reversal.quantity, -10);
+        assert_eq!(restated.reversal.unit_cost, Some(dec("5.00")));
+        assert_eq!(restated.rebooking.cost_source, Some(CostSource::Restated));
+        movements.extend([restated.reversal.clone(), restated.rebooking.clone()]);
+
+        // Stock on hand is unchanged, its value follows the new cost
+        assert_eq!(movements.iter().map(|m| m.quantity).sum::<i32>(), 14);
+        assert_eq!(value(&movements), dec("80.00"));
+
+        // The restated receipt no longer waits for review
+        let report = cost_source_report(&movements, "2025-03-01".parse().unwrap(), "2025-04-30".parse().unwrap());
+        assert!(report.needs_review.is_empty());
+        assert_eq!(report.movements.len(), 3);
+        let restated_summary = report.by_source.iter().find(|s| s.source == CostSource::Restated).unwrap();
+        assert_eq!((restated_summary.quantity, restated_summary.value), (10, dec("70.00")));
+        assert!(report.by_source.iter().all(|s| s.source != CostSource::LastReceipt));
+
+        // A movement is restated once; later changes restate the re-booking
+        assert!(restatement(&flagged, true, &request, Utc::now()).is_err());
+        assert!(restatement(&restated.reversal, false, &request, Utc::now()).is_err());
+        let again = CostRestatementRequest { unit_cost: dec("6.50"), ..request };
+        assert!(restatement(&restated.rebooking, false, &again, Utc::now()).is_ok());
+    }
+}
@@ -0,0 +1,534 @@
+//! # Optimization Explanations
+//!
+//! Every [`OptimizationResult`](super::optimization::OptimizationResult)
+//! carries a [`RecommendationExplanation`] with everything that went into it:
+//! demand statistics, forecast method and accuracy, lead time and where it
+//! came from, service level and parameter-set version, each formula with its
+//! intermediate value, and the ordering constraints that changed the result.
+//!
+//! [`compute_recommendation`] is the single place the numbers are produced,
+//! so feeding the stated inputs back into it reproduces the recommendation.
+//! Explanations are stored with the report, which keeps old recommendations
+//! explainable after the parameters change.
+
+use serde::{Deserialize, Serialize};
+
+/// Lead time used when neither a supplier catalog entry nor the item has one
+pub const DEFAULT_LEAD_TIME_DAYS: f64 = 7.0;
+
+/// Smoothing factor of the one-step forecast used to score accuracy
+pub const FORECAST_SMOOTHING_ALPHA: f64 = 0.3;
+
+/// Demand history the recommendation is based on
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DemandStatistics {
+    /// Days of history requested
+    pub window_days: i32,
+    /// Days with recorded demand inside the window
+    pub observations: usize,
+    pub mean_daily_demand: f64,
+    /// Sample variance of daily demand
+    pub variance: f64,
+    pub std_dev: f64,
+    /// Least-squares slope, units per day
+    pub trend_per_day: f64,
+}
+
+impl DemandStatistics {
+    pub fn from_daily_demand(window_days: i32, demand: &[f64]) -> Self {
+        let n = demand.len();
+        let mean = if n == 0 { 0.0 } else { demand.iter().sum::<f64>() / n as f64 };
+        let variance = if n < 2 {
+            0.0
+        } else {
+            demand.iter().map(|d| (d - mean).powi(2)).sum::<f64>() / (n - 1) as f64
+        };
+
+        Self {
+            window_days,
+            observations: n,
+            mean_daily_demand: mean,
+            variance,
+            std_dev: variance.sqrt(),
+            trend_per_day: linear_trend(demand),
+        }
+    }
+}
+
+fn linear_trend(values: &[f64]) -> f64 {
+    if values.len() < 2 {
+        return 0.0;
+    }
+    let n = values.len() as f64;
+    let sum_x: f64 = (0..values.len()).map(|i| i as f64).sum();
+    let sum_y: f64 = values.iter().sum();
+    let sum_xy: f64 = values.iter().enumerate().map(|(i, y)| i as f64 * y).sum();
+    let sum_xx: f64 = (0..values.len()).map(|i| (i as f64).powi(2)).sum();
+
+    let denominator = n * sum_xx - sum_x.powi(2);
+    if denominator.abs() < f64::EPSILON {
+        0.0
+    } else {
+        (n * sum_xy - sum_x * sum_y) / denominator
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ForecastInfo {
+    pub method: String,
+    /// `1 - WAPE` of one-step-ahead forecasts over the recent history; `None` without enough data
+    pub recent_accuracy: Option<f64>,
+    pub accuracy_window_days: usize,
+}
+
+/// Accuracy of simple exponential smoothing over the last `window` days
+pub fn one_step_forecast_accuracy(demand: &[f64], alpha: f64, window: usize) -> Option<f64> {
+    if demand.len() < 2 || window == 0 {
+        return None;
+    }
+
+    let mut forecast = demand[0];
+    let mut errors = Vec::with_capacity(demand.len() - 1);
+    for &actual in &demand[1..] {
+        errors.push(((actual - forecast).abs(), actual));
+        forecast = alpha * actual + (1.0 - alpha) * forecast;
+    }
+
+    let recent = &errors[errors.len().saturating_sub(window)..];
+    let actual_total: f64 = recent.iter().map(|(_, actual)| actual).sum();
+    if actual_total <= 0.0 {
+        return None;
+    }
+    let error_total: f64 = recent.iter().map(|(error, _)| error).sum();
+    Some((1.0 - error_total / actual_total).clamp(0.0, 1.0))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LeadTimeSource {
+    /// Fastest valid supplier catalog entry
+    SupplierCatalog,
+    /// Lead time set on the location item
+    ItemSetting,
+    /// [`DEFAULT_LEAD_TIME_DAYS`]
+    Default,
+}
+
+impl LeadTimeSource {
+    pub fn is_tracked(&self) -> bool {
+        !matches!(self, LeadTimeSource::Default)
+    }
+
+    fn describe(&self) -> &'static str {
+        match self {
+            LeadTimeSource::SupplierCatalog => "from the supplier catalog",
+            LeadTimeSource::ItemSetting => "from the item settings",
+            LeadTimeSource::Default => "default, not tracked",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LeadTime {
+    pub days: f64,
+    pub source: LeadTimeSource,
+}
+
+/// Supplier catalog lead time first, then the item setting, then the default; zero counts as unset
+pub fn resolve_lead_time(catalog_days: Option<i32>, item_days: Option<i32>) -> LeadTime {
+    if let Some(days) = catalog_days.filter(|&d| d > 0) {
+        return LeadTime { days: days as f64, source: LeadTimeSource::SupplierCatalog };
+    }
+    if let Some(days) = item_days.filter(|&d| d > 0) {
+        return LeadTime { days: days as f64, source: LeadTimeSource::ItemSetting };
+    }
+    LeadTime { days: DEFAULT_LEAD_TIME_DAYS, source: LeadTimeSource::Default }
+}
+
+/// Ordering limits that can change the economic order quantity
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct OrderConstraints {
+    pub minimum_order_quantity: Option<f64>,
+    pub pack_size: Option<f64>,
+    pub max_stock: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderConstraint {
+    MinimumOrderQuantity,
+    PackSize,
+    MaxStock,
+}
+
+/// A constraint that changed the order quantity
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConstraintAdjustment {
+    pub constraint: OrderConstraint,
+    pub limit: f64,
+    pub before: f64,
+    pub after: f64,
+}
+
+/// One formula with its inputs substituted
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FormulaStep {
+    pub quantity: String,
+    pub formula: String,
+    pub substituted: String,
+    pub value: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CalculationBreakdown {
+    /// Mean daily demand × 365
+    pub annual_demand: f64,
+    pub z_score: f64,
+    pub safety_stock: f64,
+    pub reorder_point: f64,
+    /// Before constraints
+    pub economic_order_quantity: f64,
+    /// After constraints; this is the recommendation
+    pub order_quantity: f64,
+    pub max_stock: f64,
+    pub steps: Vec<FormulaStep>,
+}
+
+/// Everything behind one recommendation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecommendationExplanation {
+    pub demand: DemandStatistics,
+    pub forecast: ForecastInfo,
+    pub lead_time: LeadTime,
+    pub service_level: f64,
+    pub parameter_set_version: u32,
+    pub ordering_cost: f64,
+    /// Holding cost per unit and year
+    pub holding_cost_rate: f64,
+    pub calculation: CalculationBreakdown,
+    pub constraints_applied: Vec<ConstraintAdjustment>,
+}
+
+/// Inputs of [`compute_recommendation`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RecommendationInputs {
+    pub mean_daily_demand: f64,
+    pub demand_std_dev: f64,
+    pub lead_time_days: f64,
+    pub service_level: f64,
+    pub ordering_cost: f64,
+    pub holding_cost_rate: f64,
+    pub constraints: OrderConstraints,
+}
+
+impl RecommendationExplanation {
+    /// The inputs stated in the explanation, for recomputing the recommendation
+    pub fn inputs(&self) -> RecommendationInputs {
+        let mut constraints = OrderConstraints::default();
+        for adjustment in &self.constraints_applied {
+            match adjustment.constraint {
+                OrderConstraint::MinimumOrderQuantity => constraints.minimum_order_quantity = Some(adjustment.limit),
+                OrderConstraint::PackSize => constraints.pack_size = Some(adjustment.limit),
+                OrderConstraint::MaxStock => constraints.max_stock = Some(adjustment.limit),
+            }
+        }
+
+        RecommendationInputs {
+            mean_daily_demand: self.demand.mean_daily_demand,
+            demand_std_dev: self.demand.std_dev,
+            lead_time_days: self.lead_time.days,
+            service_level: self.service_level,
+            ordering_cost: self.ordering_cost,
+            holding_cost_rate: self.holding_cost_rate,
+            constraints,
+        }
+    }
+
+    /// One or two sentences for the UI tooltip
+    pub fn summary(&self) -> String {
+        let calc = &self.calculation;
+        let mut text = format!(
+            "Order {:.0} units when stock falls to {:.0}. Based on {:.1} units/day average demand over the last {} days \
+             (trend {:+.2}/day), a {:.0}-day lead time ({}) and a {:.0}% service level: safety stock {:.0}, \
+             reorder point {:.0}, economic order quantity {:.0}",
+            calc.order_quantity,
+            calc.reorder_point,
+            self.demand.mean_daily_demand,
+            self.demand.window_days,
+            self.demand.trend_per_day,
+            self.lead_time.days,
+            self.lead_time.source.describe(),
+            self.service_level * 100.0,
+            calc.safety_stock,
+            calc.reorder_point,
+            calc.economic_order_quantity,
+        );
+
+        let adjustments: Vec<String> = self
+            .constraints_applied
+            .iter()
+            .map(|a| match a.constraint {
+                OrderConstraint::MinimumOrderQuantity => format!("raised to {:.0} by the minimum order quantity", a.after),
+                OrderConstraint::PackSize => format!("rounded to {:.0} for packs of {:.0}", a.after, a.limit),
+                OrderConstraint::MaxStock => format!("capped at {:.0} by the max stock of {:.0}", a.after, a.limit),
+            })
+            .collect();
+        if !adjustments.is_empty() {
+            text.push_str(", ");
+            text.push_str(&adjustments.join(", then "));
+        }
+        text.push('.');
+
+        if let Some(accuracy) = self.forecast.recent_accuracy {
+            text.push_str(&format!(
+                " The {} forecast was {:.0}% accurate over the last {} days.",
+                self.forecast.method,
+                accuracy * 100.0,
+                self.forecast.accuracy_window_days
+            ));
+        }
+        text
+    }
+}
+
+/// Inverse of the standard normal CDF (Abramowitz & Stegun 26.2.23); `0` outside (0, 1)
+pub fn inverse_normal_cdf(probability: f64) -> f64 {
+    if probability <= 0.0 || probability >= 1.0 {
+        return 0.0;
+    }
+
+    let (c0, c1, c2) = (2.515517, 0.802853, 0.010328);
+    let (d1, d2, d3) = (1.432788, 0.189269, 0.001308);
+
+    let tail = if probability > 0.5 { 1.0 - probability } else { probability };
+    let t = (-2.0 * tail.ln()).sqrt();
+    let z = t - (c0 + c1 * t + c2 * t.powi(2)) / (1.0 + d1 * t + d2 * t.powi(2) + d3 * t.powi(3));
+
+    if probability > 0.5 { z } else { -z }
+}
+
+fn step(quantity: &str, formula: &str, substituted: String, value: f64) -> FormulaStep {
+    FormulaStep {
+        quantity: quantity.to_string(),
+        formula: formula.to_string(),
+        substituted,
+        value,
+    }
+}
+
+/// Safety stock, reorder point and order quantity with constraints applied
+pub fn compute_recommendation(inputs: &RecommendationInputs) -> (CalculationBreakdown, Vec<ConstraintAdjustment>) {
+    let annual_demand = inputs.mean_daily_demand * 365.0;
+    let z_score = inverse_normal_cdf(inputs.service_level);
+    let safety_stock = (z_score * inputs.demand_std_dev * inputs.lead_time_days.sqrt()).max(0.0);
+    let reorder_point = inputs.mean_daily_demand * inputs.lead_time_days + safety_stock;
+    let eoq = if annual_demand > 0.0 && inputs.holding_cost_rate > 0.0 {
+        (2.0 * annual_demand * inputs.ordering_cost / inputs.holding_cost_rate).sqrt()
+    } else {
+        0.0
+    };
+
+    let mut adjustments = Vec::new();
+    let mut quantity = eoq;
+    let mut adjust = |constraint, limit, after: f64, quantity: &mut f64| {
+        if (after - *quantity).abs() > f64::EPSILON {
+            adjustments.push(ConstraintAdjustment {
+                constraint,
+                limit,
+                before: *quantity,
+                after,
+            });
+            *quantity = after;
+        }
+    };
+
+    if let Some(moq) = inputs.constraints.minimum_order_quantity.filter(|&m| m > 0.0) {
+        adjust(OrderConstraint::MinimumOrderQuantity, moq, quantity.max(moq), &mut quantity);
+    }
+    let pack = inputs.constraints.pack_size.filter(|&p| p > 0.0);
+    if let Some(pack) = pack {
+        adjust(OrderConstraint::PackSize, pack, (quantity / pack).ceil() * pack, &mut quantity);
+    }
+    if let Some(max_stock) = inputs.constraints.max_stock.filter(|&m| m > 0.0) {
+        let room = (max_stock - reorder_point).max(0.0);
+        if quantity > room {
+            let capped = match pack {
+                Some(pack) => (room / pack).floor() * pack,
+                None => room,
+            };
+            adjust(OrderConstraint::MaxStock, max_stock, capped, &mut quantity);
+        }
+    }
+    let max_stock = reorder_point + quantity;
+
+    let steps = vec![
+        step("annual_demand", "D = μ × 365", format!("{:.4} × 365", inputs.mean_daily_demand), annual_demand),
+        step("z_score", "z = Φ⁻¹(service level)", format!("Φ⁻¹({})", inputs.service_level), z_score),
+        step(
+            "safety_stock",
+            "SS = z × σ × √L",
+            format!("{:.4} × {:.4} × √{}", z_score, inputs.demand_std_dev, inputs.lead_time_days),
+            safety_stock,
+        ),
+        step(
+            "reorder_point",
+            "ROP = μ × L + SS",
+            format!("{:.4} × {} + {:.4}", inputs.mean_daily_demand, inputs.lead_time_days, safety_stock),
+            reorder_point,
+        ),
+        step(
+            "economic_order_quantity",
+            "EOQ = √(2 × D × S / H)",
+            format!("√(2 × {:.4} × {} / {})", annual_demand, inputs.ordering_cost, inputs.holding_cost_rate),
+            eoq,
+        ),
+        step("max_stock", "MAX = ROP + Q", format!("{:.4} + {:.4}", reorder_point, quantity), max_stock),
+    ];
+
+    (
+        CalculationBreakdown {
+            annual_demand,
+            z_score,
+            safety_stock,
+            reorder_point,
+            economic_order_quantity: eoq,
+            order_quantity: quantity,
+            max_stock,
+            steps,
+        },
+        adjustments,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn explanation(constraints: OrderConstraints) -> RecommendationExplanation {
+        let demand = [10.0, 12.0, 9.0, 14.0, 11.0, 13.0, 10.0, 12.0, 15.0, 11.0];
+        let stats = DemandStatistics::from_daily_demand(365, &demand);
+        let inputs = RecommendationInputs {
+            mean_daily_demand: stats.mean_daily_demand,
+            demand_std_dev: stats.std_dev,
+            lead_time_days: 10.0,
+            service_level: 0.95,
+            ordering_cost: 50.0,
+            holding_cost_rate: 0.25,
+            constraints,
+        };
+        let (calculation, constraints_applied) = compute_recommendation(&inputs);
+
+        RecommendationExplanation {
+            demand: stats,
+            forecast: ForecastInfo {
+                method: "Exponential smoothing".to_string(),
+                recent_accuracy: one_step_forecast_accuracy(&demand, FORECAST_SMOOTHING_ALPHA, 30),
+                accuracy_window_days: 30,
+            },
+            lead_time: LeadTime {
+                days: 10.0,
+                source: LeadTimeSource::SupplierCatalog,
+            },
+            service_level: 0.95,
+            parameter_set_version: 3,
+            ordering_cost: 50.0,
+            holding_cost_rate: 0.25,
+            calculation,
+            constraints_applied,
+        }
+    }
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-9, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn test_stated_inputs_reproduce_the_recommendation() {
+        let constraints = OrderConstraints {
+            minimum_order_quantity: Some(1500.0),
+            pack_size: Some(48.0),
+            max_stock: None,
+        };
+        let explained = explanation(constraints);
+        let calc = &explained.calculation;
+
+        // Recompute each step by hand from the stated inputs
+        let d = &explained.demand;
+        assert_close(d.mean_daily_demand, 11.7);
+        assert_close(calc.annual_demand, d.mean_daily_demand * 365.0);
+        assert_close(calc.z_score, inverse_normal_cdf(explained.service_level));
+        assert!((calc.z_score - 1.645).abs() < 0.01);
+        assert_close(calc.safety_stock, calc.z_score * d.std_dev * explained.lead_time.days.sqrt());
+        assert_close(calc.reorder_point, d.mean_daily_demand * explained.lead_time.days + calc.safety_stock);
+        assert_close(
+            calc.economic_order_quantity,
+            (2.0 * calc.annual_demand * explained.ordering_cost / explained.holding_cost_rate).sqrt(),
+        );
+
+        // EOQ ≈ 1307 → MOQ 1500 → 32 packs of 48
+        let applied: Vec<_> = explained.constraints_applied.iter().map(|a| a.constraint).collect();
+        assert_eq!(applied, vec![OrderConstraint::MinimumOrderQuantity, OrderConstraint::PackSize]);
+        assert_close(explained.constraints_applied[0].before, calc.economic_order_quantity);
+        assert_close(calc.order_quantity, 1536.0);
+        assert_close(calc.max_stock, calc.reorder_point + calc.order_quantity);
+
+        // Feeding the explanation back in gives the same numbers
+        let (again, adjustments) = compute_recommendation(&explained.inputs());
+        assert_eq!(&again, calc);
+        assert_eq!(adjustments, explained.constraints_applied);
+
+        let step = calc.steps.iter().find(|s| s.quantity == "safety_stock").unwrap();
+        assert_close(step.value, calc.safety_stock);
+    }
+
+    #[test]
+    fn test_max_stock_caps_order_quantity_to_whole_packs() {
+        let explained = explanation(OrderConstraints {
+            minimum_order_quantity: None,
+            pack_size: Some(24.0),
+            max_stock: Some(400.0),
+        });
+        let calc = &explained.calculation;
+        let room = 400.0 - calc.reorder_point;
+        assert_close(calc.order_quantity, (room / 24.0).floor() * 24.0);
+        assert!(calc.max_stock <= 400.0);
+        assert_eq!(explained.constraints_applied.last().unwrap().constraint, OrderConstraint::MaxStock);
+
+        let (again, _) = compute_recommendation(&explained.inputs());
+        assert_eq!(&again, calc);
+    }
+
+    #[test]
+    fn test_summary_names_inputs_and_constraints() {
+        let explained = explanation(OrderConstraints {
+            minimum_order_quantity: Some(1500.0),
+            pack_size: Some(48.0),
+            max_stock: None,
+        });
+        let summary = explained.summary();
+        assert!(summary.starts_with("Order 1536 units when stock falls to "));
+        assert!(summary.contains("11.7 units/day average demand over the last 365 days"));
+        assert!(summary.contains("10-day lead time (from the supplier catalog)"));
+        assert!(summary.contains("95% service level"));
+        assert!(summary.contains("raised to 1500 by the minimum order quantity, then rounded to 1536 for packs of 48"));
+        assert!(summary.contains("forecast was"));
+    }
+
+    #[test]
+    fn test_lead_time_source() {
+        assert_eq!(resolve_lead_time(Some(12), Some(5)).source, LeadTimeSource::SupplierCatalog);
+        assert_eq!(resolve_lead_time(Some(0), Some(5)), LeadTime { days: 5.0, source: LeadTimeSource::ItemSetting });
+        let fallback = resolve_lead_time(None, Some(0));
+        assert_eq!(fallback.days, DEFAULT_LEAD_TIME_DAYS);
+        assert!(!fallback.source.is_tracked());
+    }
+
+    #[test]
+    fn test_forecast_accuracy() {
+        assert_eq!(one_step_forecast_accuracy(&[5.0; 20], 0.3, 10), Some(1.0));
+        assert_eq!(one_step_forecast_accuracy(&[5.0], 0.3, 10), None);
+        assert_eq!(one_step_forecast_accuracy(&[0.0; 5], 0.3, 10), None);
+        let accuracy = one_step_forecast_accuracy(&[10.0, 20.0, 10.0, 20.0], 0.3, 10).unwrap();
+        assert!(accuracy > 0.0 && accuracy < 1.0);
+    }
+}
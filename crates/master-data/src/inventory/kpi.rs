@@ -0,0 +1,432 @@
+//! # Stock Period Figures
+//!
+//! The figures finance reports for one product over a period, computed from
+//! its costed movements: stock value at the start and end of the period under
+//! a cost flow, cost of goods sold, turnover, days inventory outstanding,
+//! stock aging and the stock left free of reservations.
+//!
+//! Movements are applied in the order they occurred. Receipts add a layer at
+//! their unit cost; an inbound movement without a cost comes in at zero, as
+//! in the [stock valuation](super::valuation). Shipments and consumption are
+//! the cost of goods sold; every other outbound movement (adjustments,
+//! damage, losses, counts) is a write-down. A reversed movement and its
+//! reversal cancel out and are left out. Amounts are booked in cents: an
+//! issue costs the difference of the rounded stock value before and after
+//! it, so opening value + received − cost of goods sold − write-downs is
+//! exactly the closing value under both cost flows.
+//!
+//! The functions here are pure. The golden tests in
+//! `tests/inventory_golden.rs` pin their results on small fixture months;
+//! changing a formula means regenerating the expected files there.
+
+use crate::error::{MasterDataError, Result};
+use crate::inventory::costing::CostedMovement;
+use crate::inventory::model::AgingCategory;
+use chrono::{DateTime, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+
+/// Decimal places of the average inventory value, turnover and days inventory outstanding
+pub const RATIO_SCALE: u32 = 4;
+
+/// Outbound movement types counted as cost of goods sold
+pub const COST_OF_SALES_TYPES: &[&str] = &["shipment", "consumption"];
+
+/// How the cost of issued units is taken from the stock
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CostFlow {
+    /// The oldest layers are issued first
+    Fifo,
+    /// Issues cost the moving average of the stock on hand
+    WeightedAverage,
+}
+
+impl CostFlow {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Fifo => "fifo",
+            Self::WeightedAverage => "weighted_average",
+        }
+    }
+}
+
+/// Units of a receipt still on hand
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StockLayer {
+    pub quantity: i64,
+    pub unit_cost: Decimal,
+    pub received_at: DateTime<Utc>,
+}
+
+/// Stock value over a period under one cost flow
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PeriodValuation {
+    pub cost_flow: CostFlow,
+    pub opening_quantity: i64,
+    pub opening_value: Decimal,
+    pub received_quantity: i64,
+    pub received_value: Decimal,
+    /// Shipments and consumption
+    pub cost_of_goods_sold: Decimal,
+    /// Every other outbound movement
+    pub write_downs: Decimal,
+    pub closing_quantity: i64,
+    pub closing_value: Decimal,
+}
+
+/// Turnover of a period. A ratio is `None` where its divisor is zero: a
+/// period without stock has no turnover, one without cost of goods sold
+/// has no days inventory outstanding.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PeriodKpis {
+    /// Calendar days of the period, both ends included
+    pub days: i64,
+    /// (opening value + closing value) / 2
+    pub average_inventory_value: Decimal,
+    pub cost_of_goods_sold: Decimal,
+    /// Cost of goods sold / average inventory value
+    pub turnover: Option<Decimal>,
+    /// Average inventory value / cost of goods sold × days
+    pub days_inventory_outstanding: Option<Decimal>,
+}
+
+/// Stock on hand of one aging category, at the cost of its receipts
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AgingBucket {
+    pub category: AgingCategory,
+    pub quantity: i64,
+    pub value: Decimal,
+}
+
+/// Everything reported for a product over a period
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PeriodReport {
+    pub from: NaiveDate,
+    /// Inclusive
+    pub to: NaiveDate,
+    pub valuation: PeriodValuation,
+    pub kpis: PeriodKpis,
+    /// Closing stock by the age of its receipt on `to`; every category is listed
+    pub aging: Vec<AgingBucket>,
+    pub reserved: i64,
+    /// Closing quantity not reserved, never below zero
+    pub available: i64,
+}
+
+fn cents(amount: Decimal) -> Decimal {
+    amount.round_dp(2)
+}
+
+/// Stock of one product as movements are applied
+struct Stock {
+    cost_flow: CostFlow,
+    /// Oldest first; issued from the front under both cost flows, which is the
+    /// physical flow the aging is based on
+    layers: VecDeque<StockLayer>,
+    quantity: i64,
+    value: Decimal,
+}
+
+impl Stock {
+    fn new(cost_flow: CostFlow) -> Self {
+        Self {
+            cost_flow,
+            layers: VecDeque::new(),
+            quantity: 0,
+            value: Decimal::ZERO,
+        }
+    }
+
+    fn layer_value(&self) -> Decimal {
+        cents(self.layers.iter().map(|layer| layer.unit_cost * Decimal::from(layer.quantity)).sum())
+    }
+
+    /// Value added
+    fn receive(&mut self, quantity: i64, unit_cost: Decimal, received_at: DateTime<Utc>) -> Decimal {
+        self.layers.push_back(StockLayer { quantity, unit_cost, received_at });
+        self.quantity += quantity;
+        let added = match self.cost_flow {
+            CostFlow::Fifo => self.layer_value() - self.value,
+            CostFlow::WeightedAverage => cents(unit_cost * Decimal::from(quantity)),
+        };
+        self.value += added;
+        added
+    }
+
+    /// Cost of the units issued
+    fn issue(&mut self, movement: &CostedMovement) -> Result<Decimal> {
+        let quantity = -(movement.quantity as i64);
+        if quantity > self.quantity {
+            return Err(MasterDataError::ValidationError {
+                field: "quantity".to_string(),
+                message: format!(
+                    "Movement {} takes out {} units with only {} in stock",
+                    movement.id, quantity, self.quantity
+                ),
+            });
+        }
+
+        let mut remaining = quantity;
+        while remaining > 0 {
+            let Some(layer) = self.layers.front_mut() else { break };
+            let taken = remaining.min(layer.quantity);
+            layer.quantity -= taken;
+            remaining -= taken;
+            if layer.quantity == 0 {
+                self.layers.pop_front();
+            }
+        }
+
+        let cost = match self.cost_flow {
+            CostFlow::Fifo => self.value - self.layer_value(),
+            CostFlow::WeightedAverage if quantity == self.quantity => self.value,
+            CostFlow::WeightedAverage => cents(self.value * Decimal::from(quantity) / Decimal::from(self.quantity)),
+        };
+        self.quantity -= quantity;
+        self.value -= cost;
+        Ok(cost)
+    }
+}
+
+/// The movements of one product up to the end of `to`, in the order they
+/// occurred, without reversed movements and their reversals
+fn applicable(movements: &[CostedMovement], to: NaiveDate) -> Vec<&CostedMovement> {
+    let reversed: HashSet<_> = movements.iter().filter_map(|m| m.reversal_of).collect();
+    let mut applicable: Vec<&CostedMovement> = movements
+        .iter()
+        .filter(|m| m.reversal_of.is_none() && !reversed.contains(&m.id))
+        .filter(|m| m.occurred_at.date_naive() <= to)
+        .collect();
+    applicable.sort_by_key(|m| m.occurred_at);
+    applicable
+}
+
+fn is_cost_of_sales(movement: &CostedMovement) -> bool {
+    COST_OF_SALES_TYPES.contains(&movement.movement_type.as_str())
+}
+
+/// Value the movements of one product over `from` to `to`; the movements
+/// before `from` make up the opening stock. Also returns the layers on hand
+/// at the end of the period.
+pub fn value_period(
+    movements: &[CostedMovement],
+    cost_flow: CostFlow,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<(PeriodValuation, Vec<StockLayer>)> {
+    let mut stock = Stock::new(cost_flow);
+    let mut opening: Option<(i64, Decimal)> = None;
+    let mut valuation = PeriodValuation {
+        cost_flow,
+        opening_quantity: 0,
+        opening_value: Decimal::ZERO,
+        received_quantity: 0,
+        received_value: Decimal::ZERO,
+        cost_of_goods_sold: Decimal::ZERO,
+        write_downs: Decimal::ZERO,
+        closing_quantity: 0,
+        closing_value: Decimal::ZERO,
+    };
+
+    for movement in applicable(movements, to) {
+        let in_period = movement.occurred_at.date_naive() >= from;
+        if in_period && opening.is_none() {
+            opening = Some((stock.quantity, stock.value));
+        }
+
+        if movement.quantity > 0 {
+            let quantity = movement.quantity as i64;
+            let added = stock.receive(quantity, movement.unit_cost.unwrap_or(Decimal::ZERO), movement.occurred_at);
+            if in_period {
+                valuation.received_quantity += quantity;
+                valuation.received_value += added;
+            }
+        } else if movement.quantity < 0 {
+            let cost = stock.issue(movement)?;
+            if in_period && is_cost_of_sales(movement) {
+                valuation.cost_of_goods_sold += cost;
+            } else if in_period {
+                valuation.write_downs += cost;
+            }
+        }
+    }
+
+    let (opening_quantity, opening_value) = opening.unwrap_or((stock.quantity, stock.value));
+    valuation.opening_quantity = opening_quantity;
+    valuation.opening_value = opening_value;
+    valuation.closing_quantity = stock.quantity;
+    valuation.closing_value = stock.value;
+    Ok((valuation, stock.layers.into()))
+}
+
+/// Turnover and days inventory outstanding of a valued period
+pub fn period_kpis(valuation: &PeriodValuation, from: NaiveDate, to: NaiveDate) -> PeriodKpis {
+    let days = (to - from).num_days() + 1;
+    let average = ((valuation.opening_value + valuation.closing_value) / Decimal::TWO).round_dp(RATIO_SCALE);
+    let cogs = valuation.cost_of_goods_sold;
+
+    PeriodKpis {
+        days,
+        average_inventory_value: average,
+        cost_of_goods_sold: cogs,
+        turnover: (!average.is_zero()).then(|| (cogs / average).round_dp(RATIO_SCALE)),
+        days_inventory_outstanding: (!cogs.is_zero())
+            .then(|| (average / cogs * Decimal::from(days)).round_dp(RATIO_SCALE)),
+    }
+}
+
+/// Category of stock received `age_days` ago
+pub fn aging_category(age_days: i64) -> AgingCategory {
+    match age_days {
+        ..=30 => AgingCategory::Current,
+        31..=90 => AgingCategory::Slow,
+        91..=180 => AgingCategory::Dead,
+        _ => AgingCategory::VeryDead,
+    }
+}
+
+/// The layers on hand by the age of their receipt on `as_of`
+pub fn stock_aging(layers: &[StockLayer], as_of: NaiveDate) -> Vec<AgingBucket> {
+    let mut buckets: Vec<AgingBucket> = [
+        AgingCategory::Current,
+        AgingCategory::Slow,
+        AgingCategory::Dead,
+        AgingCategory::VeryDead,
+    ]
+    .into_iter()
+    .map(|category| AgingBucket {
+        category,
+        quantity: 0,
+        value: Decimal::ZERO,
+    })
+    .collect();
+
+    for layer in layers {
+        let category = aging_category((as_of - layer.received_at.date_naive()).num_days());
+        if let Some(bucket) = buckets.iter_mut().find(|bucket| bucket.category == category) {
+            bucket.quantity += layer.quantity;
+            bucket.value += cents(layer.unit_cost * Decimal::from(layer.quantity));
+        }
+    }
+    buckets
+}
+
+/// Units sold (shipped or consumed) on each day of `from` to `to`, days
+/// without sales included as zero; the demand history of the period
+pub fn daily_demand(movements: &[CostedMovement], from: NaiveDate, to: NaiveDate) -> Vec<f64> {
+    let mut demand = vec![0.0; ((to - from).num_days() + 1).max(0) as usize];
+    for movement in applicable(movements, to) {
+        let day = movement.occurred_at.date_naive();
+        if day >= from && movement.quantity < 0 && is_cost_of_sales(movement) {
+            demand[(day - from).num_days() as usize] += -(movement.quantity as f64);
+        }
+    }
+    demand
+}
+
+/// Value, turnover and aging of one product's stock over `from` to `to`,
+/// with `reserved` units of the closing stock reserved
+pub fn period_report(
+    movements: &[CostedMovement],
+    cost_flow: CostFlow,
+    from: NaiveDate,
+    to: NaiveDate,
+    reserved: i64,
+) -> Result<PeriodReport> {
+    if to < from {
+        return Err(MasterDataError::ValidationError {
+            field: "to".to_string(),
+            message: format!("The period ends on {} before it starts on {}", to, from),
+        });
+    }
+
+    let (valuation, layers) = value_period(movements, cost_flow, from, to)?;
+    Ok(PeriodReport {
+        from,
+        to,
+        kpis: period_kpis(&valuation, from, to),
+        aging: stock_aging(&layers, to),
+        reserved,
+        available: (valuation.closing_quantity - reserved).max(0),
+        valuation,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use uuid::Uuid;
+
+    fn dec(value: &str) -> Decimal {
+        value.parse().unwrap()
+    }
+
+    fn date(value: &str) -> NaiveDate {
+        value.parse().unwrap()
+    }
+
+    fn movement(day: &str, movement_type: &str, quantity: i32, unit_cost: Option<&str>) -> CostedMovement {
+        CostedMovement {
+            id: Uuid::new_v4(),
+            product_id: Uuid::nil(),
+            location_id: Uuid::nil(),
+            movement_type: movement_type.to_string(),
+            quantity,
+            unit_cost: unit_cost.map(dec),
+            cost_source: None,
+            cost_needs_review: false,
+            reversal_of: None,
+            restatement_of: None,
+            reference_document: None,
+            reference_number: None,
+            reason: None,
+            occurred_at: Utc.from_utc_datetime(&date(day).and_hms_opt(12, 0, 0).unwrap()),
+        }
+    }
+
+    #[test]
+    fn test_issuing_more_than_on_hand_fails() {
+        let movements = vec![
+            movement("2026-03-02", "receipt", 5, Some("1.00")),
+            movement("2026-03-03", "shipment", -6, None),
+        ];
+        let result = value_period(&movements, CostFlow::Fifo, date("2026-03-01"), date("2026-03-31"));
+        assert!(matches!(result, Err(MasterDataError::ValidationError { .. })));
+    }
+
+    #[test]
+    fn test_a_reversed_receipt_is_left_out() {
+        let receipt = movement("2026-03-02", "receipt", 10, Some("3.00"));
+        let mut reversal = movement("2026-03-05", "receipt", -10, Some("3.00"));
+        reversal.reversal_of = Some(receipt.id);
+        let movements = vec![receipt, reversal, movement("2026-03-06", "receipt", 4, Some("2.50"))];
+
+        let (valuation, _) =
+            value_period(&movements, CostFlow::WeightedAverage, date("2026-03-01"), date("2026-03-31")).unwrap();
+        assert_eq!(valuation.received_quantity, 4);
+        assert_eq!(valuation.closing_value, dec("10.00"));
+        assert_eq!(valuation.write_downs, Decimal::ZERO);
+    }
+
+    #[test]
+    fn test_a_three_way_split_books_whole_cents_and_reconciles() {
+        let movements = vec![
+            movement("2026-03-02", "receipt", 3, Some("3.3333")),
+            movement("2026-03-03", "shipment", -1, None),
+            movement("2026-03-04", "shipment", -1, None),
+            movement("2026-03-05", "shipment", -1, None),
+        ];
+        for cost_flow in [CostFlow::Fifo, CostFlow::WeightedAverage] {
+            let (v, _) = value_period(&movements, cost_flow, date("2026-03-01"), date("2026-03-31")).unwrap();
+            assert_eq!(v.cost_of_goods_sold, dec("10.00"), "{:?}", cost_flow);
+            assert_eq!(v.closing_value, Decimal::ZERO, "{:?}", cost_flow);
+            assert_eq!(
+                v.opening_value + v.received_value - v.cost_of_goods_sold - v.write_downs,
+                v.closing_value
+            );
+        }
+    }
+}
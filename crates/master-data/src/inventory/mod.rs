@@ -9,6 +9,22 @@ pub mod repository;
 pub mod service;
 pub mod analytics;
 pub mod optimization;
+pub mod explanation;
+pub mod serial;
+pub mod stocktake;
+pub mod returns;
+pub mod scenario;
+pub mod transfer_approval;
+pub mod reservation_drift;
+pub mod picking;
+pub mod capacity;
+pub mod receiving;
+pub mod valuation;
+pub mod consignment;
+pub mod atp;
+pub mod snapshot;
+pub mod costing;
+pub mod kpi;
 
 #[cfg(feature = "axum")]
 pub mod handlers;
@@ -18,7 +34,7 @@ pub use model::{
     LocationInventory, InventoryMovement, StockTransfer, ReplenishmentRule,
     InventorySnapshot, LocationType, MovementType, TransferStatus, TransferPriority,
     ABCClassification, MovementVelocity, StorageRequirements,
-    ForecastMethod, ForecastAccuracy, UpdateInventoryRequest,
+    ForecastMethod, ForecastAccuracy, UpdateInventoryRequest, StockAvailability,
     InventoryOptimization, OptimizationAction, CycleCount, CountStatus,
     StockAgingItem, AgingCategory, InventoryForecast,
     // Add other inventory-specific types
@@ -32,13 +48,110 @@ pub use model::{
 };
 
 pub use repository::{
-    InventoryRepository, PostgresInventoryRepository,
+    InventoryRepository, PostgresInventoryRepository, InventoryAlertPublisher,
     TurnoverAnalysisItem, TurnoverClassification,
+    SerialUnitRepository, PostgresSerialUnitRepository, SerialChangeSet,
+    OptimizationReportRepository, PostgresOptimizationReportRepository,
+    StocktakeRepository, PostgresStocktakeRepository,
+    ReturnOrderRepository, PostgresReturnOrderRepository, ReturnOrderChange,
+    ForecastScenarioRepository, PostgresForecastScenarioRepository,
+    StockTransferRepository, PostgresStockTransferRepository,
+    ReservationReconciliationRepository, PostgresReservationReconciliationRepository,
+    PickWaveRepository, PostgresPickWaveRepository,
+    LocationCapacityRepository, PostgresLocationCapacityRepository,
+    PurchaseReceiptRepository, PostgresPurchaseReceiptRepository,
+    InventoryValuationRepository, PostgresInventoryValuationRepository, StockValueFilter,
+    ConsignmentRepository, PostgresConsignmentRepository,
+    AvailableToPromiseRepository, PostgresAvailableToPromiseRepository,
+    InventorySnapshotRepository, PostgresInventorySnapshotRepository,
+    InventoryCostingRepository, PostgresInventoryCostingRepository,
 };
 
 pub use service::{
     InventoryService, DefaultInventoryService,
     CreateStockTransferRequest, CreateReservationRequest,
+    SerialTrackingService, DefaultSerialTrackingService,
+    ReceiveSerialsRequest, OutboundSerialsRequest, ReturnSerialsRequest, TransferSerialsRequest,
+    StocktakeService, DefaultStocktakeService,
+    ReturnsService, DefaultReturnsService,
+    ForecastScenarioService, DefaultForecastScenarioService,
+    StockTransferService, DefaultStockTransferService,
+    PickingService, DefaultPickingService,
+    LocationCapacityService, DefaultLocationCapacityService,
+    PurchaseReceiptService, DefaultPurchaseReceiptService,
+    InventoryValuationService, DefaultInventoryValuationService,
+    ConsignmentService, DefaultConsignmentService,
+    AvailableToPromiseService, DefaultAvailableToPromiseService,
+    InventorySnapshotService, DefaultInventorySnapshotService,
+    InventoryCostingService, DefaultInventoryCostingService,
+};
+
+pub use serial::{
+    SerialUnit, SerialStatus, SerialEvent, SerialHistory, SerialEventContext,
+};
+
+pub use stocktake::{
+    StocktakeSession, StocktakeStatus, StocktakeLine, StocktakeImportResult, CreateStocktakeSessionRequest,
+    ScanLine, ScanLineError, UnmatchedBarcode, LocationShrinkage, FileLineChanges,
+};
+
+pub use returns::{
+    ReturnOrder, ReturnOrderLine, ReturnStatus, ReturnReason, Disposition, CreateReturnOrderRequest,
+    CreateReturnLineRequest, ReceiveReturnRequest, InspectReturnRequest, LineInspection, ProductReturnRate,
+};
+
+pub use scenario::{
+    ForecastScenario, ForecastScenarioRequest, ForecastScenarioComparison, ScenarioItem, ScenarioItemResult,
+    ScenarioItemComparison, DemandUplift, UpliftKind, ProjectedDay, IncomingSupply, SupplySource,
+};
+
+pub use transfer_approval::{
+    TransferApprovalRule, TransferApproval, TransferRecord, TransferListFilter, ApprovalDecision,
+    TransferFacts, ReservationChange, TRANSFER_APPROVAL_PERMISSION, TRANSFER_RULES_PERMISSION, ANY_REGION,
+};
+
+pub use reservation_drift::{
+    ReservationDrift, ReservationAdjustment, RepairOutcome, ReconciliationScope, SWEEP_SLICES,
+};
+
+pub use picking::{
+    PickWave, PickLine, PickLineStatus, WaveStatus, WaveSelection, WaveSummary, PickException, ShortPickResolution,
+    CreatePickWaveRequest, ConfirmPickRequest, PickPathStrategy, SerpentinePath,
+};
+
+pub use capacity::{
+    CapacityUnit, LocationCapacity, LocationCapacityInput, CapacitySettings, ProductSpace, SpaceFactor, StoredQuantity,
+    IncomingQuantity, CapacityLevel, CapacityUsage, CapacityAssessment, CAPACITY_OVERRIDE_PERMISSION,
+};
+
+pub use receiving::{
+    ReceivableOrder, ReceivableLine, ReceivePurchaseOrderRequest, ReceiptLineRequest, ReceiptLine, PurchaseOrderReceipt,
+    ReceivingSettings, LineStatus, LineUpdate, ReceiptEvent, ReceiptEventKind, ReceiptBooking, ReceiptHistory,
+    LineReceiptHistory,
+};
+
+pub use valuation::{
+    StockValueLine, ValuedLine, StockValuation, StockKpis, ProductMargin, MarginReport, LocationValue, ProductValue,
+    ValuationDashboard, DASHBOARD_TOP_PRODUCTS,
+};
+
+pub use kpi::{
+    CostFlow, StockLayer, PeriodValuation, PeriodKpis, AgingBucket, PeriodReport, COST_OF_SALES_TYPES,
+};
+
+pub use consignment::{
+    ConsignmentMovementKind, ConsumptionKind, ConsignmentMovement, ConsignmentBalance, ConsignmentReceiptRequest,
+    ConsignmentConsumptionRequest, ConsignmentTransferRequest, ConsignmentPrice, ConsumedProduct, ConsignmentSettlement,
+};
+
+pub use atp::{
+    AtpStock, ScheduledReceipt, AtpElementKind, AtpElement, AtpPoint, AtpTimeline, AtpBatchLine, AtpLineResult,
+    PURCHASE_ORDER_STATUSES, TRANSFER_STATUSES,
+};
+
+pub use snapshot::{
+    SnapshotScope, SnapshotTrigger, SnapshotRun, SnapshotLine, SnapshotDifference, SnapshotComparison,
+    SnapshotSchedule, MAX_SCOPE_PRODUCTS,
 };
 
 pub use analytics::{
@@ -52,4 +165,9 @@ pub use optimization::{
     OptimizationResult, DemandForecast, SupplyChainOptimization,
     OptimizationParameters, InventoryOptimizationReport,
     // Other optimization types
+};
+
+pub use explanation::{
+    RecommendationExplanation, DemandStatistics, ForecastInfo, LeadTime, LeadTimeSource,
+    OrderConstraints, OrderConstraint, ConstraintAdjustment, CalculationBreakdown, FormulaStep,
 };
\ No newline at end of file
@@ -8,6 +8,7 @@ use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use uuid::Uuid;
 use std::collections::HashMap;
+use crate::error::{MasterDataError, Result};
 use crate::types::{ValuationMethod, ReservationType};
 use rust_decimal::Decimal;
 
@@ -57,9 +58,11 @@ pub enum LocationType {
     Customer,
     Transit,
     Virtual,
+    /// Returned or blocked goods awaiting inspection; never sellable
+    Quarantine,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, sqlx::Type)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
 #[sqlx(type_name = "abc_classification", rename_all = "snake_case")]
 pub enum ABCClassification {
     A, // High value, high frequency
@@ -198,6 +201,10 @@ pub struct StockTransfer {
     pub shipped_date: Option<DateTime<Utc>>,
     pub received_date: Option<DateTime<Utc>>,
     pub actual_delivery_date: Option<DateTime<Utc>>,
+    /// When the receiving location expects the goods; available-to-promise
+    /// counts inbound transfers from this day
+    #[serde(default)]
+    pub expected_arrival_date: Option<NaiveDate>,
     pub tracking_number: Option<String>,
     pub carrier: Option<String>,
     pub shipping_cost: Option<f64>,
@@ -209,6 +216,8 @@ pub struct StockTransfer {
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, sqlx::Type)]
 #[sqlx(type_name = "transfer_status", rename_all = "snake_case")]
 pub enum TransferStatus {
+    /// Matched a tenant approval rule; stock is reserved but not released for picking
+    PendingApproval,
     Requested,
     Approved,
     Rejected,
@@ -219,6 +228,83 @@ pub enum TransferStatus {
     Pending,
 }
 
+impl TransferStatus {
+    /// Name of the value in the `transfer_status` database type
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TransferStatus::PendingApproval => "pending_approval",
+            TransferStatus::Requested => "requested",
+            TransferStatus::Approved => "approved",
+            TransferStatus::Rejected => "rejected",
+            TransferStatus::InTransit => "in_transit",
+            TransferStatus::PartiallyReceived => "partially_received",
+            TransferStatus::Completed => "completed",
+            TransferStatus::Cancelled => "cancelled",
+            TransferStatus::Pending => "pending",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "pending_approval" => Some(TransferStatus::PendingApproval),
+            "requested" => Some(TransferStatus::Requested),
+            "approved" => Some(TransferStatus::Approved),
+            "rejected" => Some(TransferStatus::Rejected),
+            "in_transit" => Some(TransferStatus::InTransit),
+            "partially_received" => Some(TransferStatus::PartiallyReceived),
+            "completed" => Some(TransferStatus::Completed),
+            "cancelled" => Some(TransferStatus::Cancelled),
+            "pending" => Some(TransferStatus::Pending),
+            _ => None,
+        }
+    }
+}
+
+impl StockTransfer {
+    /// Quantity that left the source and has not been received yet
+    pub fn quantity_outstanding(&self) -> i32 {
+        self.quantity_shipped.unwrap_or(self.quantity) - self.quantity_received.unwrap_or(0)
+    }
+
+    /// Book a receipt of `quantity` at the destination
+    ///
+    /// Receipts add up; the transfer is `partially_received` until everything
+    /// shipped has arrived and `completed` afterwards.
+    pub fn record_receipt(&mut self, quantity: i32, received_by: Uuid, received_at: DateTime<Utc>) -> Result<()> {
+        if !matches!(self.status, TransferStatus::InTransit | TransferStatus::PartiallyReceived) {
+            return Err(MasterDataError::InvalidTransferTransition {
+                transfer_id: self.id.to_string(),
+                from: self.status.as_str().to_string(),
+                to: TransferStatus::Completed.as_str().to_string(),
+            });
+        }
+        if quantity <= 0 {
+            return Err(MasterDataError::ValidationError {
+                field: "quantity_received".to_string(),
+                message: "Received quantity must be positive".to_string(),
+            });
+        }
+        let outstanding = self.quantity_outstanding();
+        if quantity > outstanding {
+            return Err(MasterDataError::ValidationError {
+                field: "quantity_received".to_string(),
+                message: format!("Received quantity {} exceeds the {} still in transit", quantity, outstanding),
+            });
+        }
+
+        self.quantity_received = Some(self.quantity_received.unwrap_or(0) + quantity);
+        self.received_by = Some(received_by);
+        self.received_date = Some(received_at);
+        if quantity == outstanding {
+            self.status = TransferStatus::Completed;
+            self.actual_delivery_date = Some(received_at);
+        } else {
+            self.status = TransferStatus::PartiallyReceived;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::Type)]
 #[sqlx(type_name = "transfer_priority", rename_all = "snake_case")]
 pub enum TransferPriority {
@@ -229,8 +315,38 @@ pub enum TransferPriority {
     Emergency,
 }
 
+/// Stock figures of one product at one location, without planning parameters
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StockAvailability {
+    pub product_id: Uuid,
+    pub location_id: Uuid,
+    pub quantity_available: i32,
+    pub quantity_reserved: i32,
+    pub quantity_on_order: i32,
+    pub quantity_in_transit: i32,
+    /// Part of `quantity_available` owned by suppliers; it is sold like our
+    /// own stock, but only valued once consumed
+    pub consignment_quantity: i32,
+    /// Part of `quantity_available` in failed or recalled batches awaiting
+    /// disposition
+    pub blocked_quantity: i32,
+    /// False for quarantine locations, whose stock is held back from sale
+    pub sellable: bool,
+}
+
+impl StockAvailability {
+    /// Quantity that can still be promised to new orders
+    pub fn available_to_promise(&self) -> i32 {
+        if !self.sellable {
+            return 0;
+        }
+        (self.quantity_available - self.quantity_reserved - self.blocked_quantity).max(0)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UpdateInventoryRequest {
+    pub product_id: Uuid,
     pub location_id: Uuid,
     pub quantity_change: i32,
     pub movement_type: MovementType,
@@ -418,6 +534,7 @@ pub enum OrderStatus {
     Ordered,
     PartiallyReceived,
     Received,
+    ClosedShort,
     Cancelled,
     Pending,
 }
@@ -833,11 +950,119 @@ pub struct StockAgingItem {
     pub suggested_action: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, sqlx::Type)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
 #[sqlx(type_name = "aging_category", rename_all = "snake_case")]
 pub enum AgingCategory {
     Current,      // 0-30 days
     Slow,         // 31-90 days
     Dead,         // 90+ days
     VeryDead,     // 180+ days
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn availability(sellable: bool) -> StockAvailability {
+        StockAvailability {
+            product_id: Uuid::new_v4(),
+            location_id: Uuid::new_v4(),
+            quantity_available: 10,
+            quantity_reserved: 3,
+            quantity_on_order: 0,
+            quantity_in_transit: 0,
+            consignment_quantity: 0,
+            blocked_quantity: 0,
+            sellable,
+        }
+    }
+
+    #[test]
+    fn test_available_to_promise_nets_reservations() {
+        assert_eq!(availability(true).available_to_promise(), 7);
+    }
+
+    #[test]
+    fn test_quarantined_stock_is_not_available_to_promise() {
+        assert_eq!(availability(false).available_to_promise(), 0);
+    }
+
+    #[test]
+    fn test_blocked_batches_are_not_available_to_promise() {
+        let mut availability = availability(true);
+        availability.blocked_quantity = 4;
+        assert_eq!(availability.available_to_promise(), 3);
+        availability.blocked_quantity = 9;
+        assert_eq!(availability.available_to_promise(), 0);
+    }
+
+    fn shipped_transfer(quantity: i32) -> StockTransfer {
+        StockTransfer {
+            id: Uuid::new_v4(),
+            product_id: Uuid::new_v4(),
+            from_location_id: Uuid::new_v4(),
+            to_location_id: Uuid::new_v4(),
+            quantity,
+            quantity_shipped: Some(quantity),
+            quantity_received: None,
+            status: TransferStatus::InTransit,
+            priority: TransferPriority::Normal,
+            reason: "Rebalance".to_string(),
+            requested_by: Uuid::new_v4(),
+            approved_by: None,
+            shipped_by: None,
+            received_by: None,
+            requested_date: Utc::now(),
+            approved_date: None,
+            shipped_date: Some(Utc::now()),
+            received_date: None,
+            actual_delivery_date: None,
+            expected_arrival_date: None,
+            tracking_number: None,
+            carrier: None,
+            shipping_cost: None,
+            notes: None,
+            created_at: Utc::now(),
+            created_by: Uuid::new_v4(),
+        }
+    }
+
+    #[test]
+    fn test_partial_receipts_add_up_to_completion() {
+        let mut transfer = shipped_transfer(10);
+        let receiver = Uuid::new_v4();
+
+        transfer.record_receipt(4, receiver, Utc::now()).unwrap();
+        assert_eq!(transfer.status, TransferStatus::PartiallyReceived);
+        assert_eq!(transfer.quantity_received, Some(4));
+        assert_eq!(transfer.quantity_outstanding(), 6);
+        assert!(transfer.actual_delivery_date.is_none());
+
+        transfer.record_receipt(6, receiver, Utc::now()).unwrap();
+        assert_eq!(transfer.status, TransferStatus::Completed);
+        assert_eq!(transfer.quantity_received, Some(10));
+        assert!(transfer.actual_delivery_date.is_some());
+    }
+
+    #[test]
+    fn test_receipt_cannot_exceed_the_quantity_in_transit() {
+        let mut transfer = shipped_transfer(10);
+        transfer.record_receipt(7, Uuid::new_v4(), Utc::now()).unwrap();
+
+        let err = transfer.record_receipt(4, Uuid::new_v4(), Utc::now()).unwrap_err();
+        assert!(matches!(err, MasterDataError::ValidationError { ref field, .. } if field == "quantity_received"));
+        assert!(transfer.record_receipt(0, Uuid::new_v4(), Utc::now()).is_err());
+        assert_eq!(transfer.quantity_received, Some(7));
+    }
+
+    #[test]
+    fn test_only_shipped_transfers_can_be_received() {
+        let mut transfer = shipped_transfer(10);
+        transfer.status = TransferStatus::Pending;
+        let err = transfer.record_receipt(10, Uuid::new_v4(), Utc::now()).unwrap_err();
+        assert!(matches!(err, MasterDataError::InvalidTransferTransition { .. }));
+
+        let mut completed = shipped_transfer(5);
+        completed.record_receipt(5, Uuid::new_v4(), Utc::now()).unwrap();
+        assert!(completed.record_receipt(1, Uuid::new_v4(), Utc::now()).is_err());
+    }
+}
@@ -6,6 +6,9 @@ use std::collections::HashMap;
 use uuid::Uuid;
 
 use crate::error::{MasterDataError, Result};
+use super::capacity::{self, CapacitySettings};
+use super::explanation::*;
+use super::repository::{LocationCapacityRepository, PostgresLocationCapacityRepository};
 use super::model::*;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -20,6 +23,13 @@ pub struct OptimizationParameters {
     pub trend_factor: f64,
     pub max_inventory_investment: Option<f64>,
     pub storage_constraints: HashMap<Uuid, f64>,
+    /// Bumped whenever the parameter set is changed; recorded in each explanation
+    #[serde(default = "default_parameter_set_version")]
+    pub version: u32,
+}
+
+fn default_parameter_set_version() -> u32 {
+    1
 }
 
 impl Default for OptimizationParameters {
@@ -35,6 +45,7 @@ impl Default for OptimizationParameters {
             trend_factor: 1.0,
             max_inventory_investment: None,
             storage_constraints: HashMap::new(),
+            version: default_parameter_set_version(),
         }
     }
 }
@@ -56,10 +67,13 @@ pub struct OptimizationResult {
     pub optimization_method: String,
     pub last_updated: DateTime<Utc>,
     pub validity_period_days: i32,
+    /// Inputs, formulas and constraints behind the recommended values
+    pub explanation: RecommendationExplanation,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InventoryOptimizationReport {
+    pub id: Uuid,
     pub location_id: Uuid,
     pub optimization_date: DateTime<Utc>,
     pub total_products_analyzed: usize,
@@ -176,6 +190,9 @@ pub trait InventoryOptimizationEngine: Send + Sync {
         forecast_horizon_days: i32,
     ) -> Result<DemandForecast>;
 
+    /// Replenishment lead time of a product at a location, with where it came from
+    async fn get_lead_time(&self, product_id: Uuid, location_id: Uuid) -> Result<LeadTime>;
+
     async fn calculate_economic_order_quantity(
         &self,
         product_id: Uuid,
@@ -331,11 +348,84 @@ pub struct SeasonalInventoryAdjustment {
 
 pub struct PostgresInventoryOptimizationEngine {
     pool: Pool<Postgres>,
+    capacity_settings: CapacitySettings,
 }
 
 impl PostgresInventoryOptimizationEngine {
     pub fn new(pool: Pool<Postgres>) -> Self {
-        Self { pool }
+        Self {
+            pool,
+            capacity_settings: CapacitySettings::default(),
+        }
+    }
+
+    /// How product space is derived when rebalancing checks free capacity
+    pub fn with_capacity_settings(mut self, settings: CapacitySettings) -> Self {
+        self.capacity_settings = settings;
+        self
+    }
+
+    /// Cap the transfers proposed into each location by its free capacity,
+    /// dropping those that no longer move anything
+    async fn fit_transfers_into_capacity(
+        &self,
+        transfers: Vec<RecommendedStockTransfer>,
+    ) -> Result<Vec<RecommendedStockTransfer>> {
+        let repository = PostgresLocationCapacityRepository::new(self.pool.clone());
+        let mut targets: Vec<Uuid> = transfers.iter().map(|t| t.to_location_id).collect();
+        targets.sort();
+        targets.dedup();
+
+        let mut fitted = Vec::with_capacity(transfers.len());
+        for location_id in targets {
+            let mut proposed: Vec<RecommendedStockTransfer> =
+                transfers.iter().filter(|t| t.to_location_id == location_id).cloned().collect();
+            let capacities = match repository.location_tenant(location_id).await? {
+                Some(tenant_id) => {
+                    let capacities = repository.get_capacities(tenant_id, location_id).await?;
+                    Some(tenant_id).filter(|_| !capacities.is_empty()).map(|tenant_id| (tenant_id, capacities))
+                }
+                None => None,
+            };
+            let Some((tenant_id, capacities)) = capacities else {
+                fitted.extend(proposed);
+                continue;
+            };
+
+            let stored = repository.get_stored_quantities(tenant_id, location_id).await?;
+            let mut product_ids: Vec<Uuid> = stored
+                .iter()
+                .map(|item| item.product_id)
+                .chain(proposed.iter().map(|t| t.product_id))
+                .collect();
+            product_ids.sort();
+            product_ids.dedup();
+            let products = repository.get_product_space(tenant_id, &product_ids).await?;
+            let wanted: Vec<(Uuid, i64)> = proposed
+                .iter()
+                .map(|t| (t.product_id, t.recommended_quantity.floor() as i64))
+                .collect();
+            let fitting = capacity::fit_into_free_capacity(
+                location_id,
+                &capacities,
+                &stored,
+                &wanted,
+                &products,
+                &self.capacity_settings,
+            );
+
+            for (transfer, quantity) in proposed.iter_mut().zip(fitting) {
+                if (quantity as f64) < transfer.recommended_quantity {
+                    let quantity = quantity as f64;
+                    transfer.transfer_cost = quantity * 0.5;
+                    transfer.expected_benefit = quantity * 2.0;
+                    transfer.recommended_quantity = quantity;
+                    transfer.reason = format!("{}, limited by free capacity at the target", transfer.reason);
+                }
+            }
+            fitted.extend(proposed.into_iter().filter(|t| t.recommended_quantity >= 1.0));
+        }
+        Ok(fitted)
     }
 
     async fn get_historical_demand_data(
@@ -469,28 +559,48 @@ impl PostgresInventoryOptimizationEngine {
     }
 
     async fn normal_distribution_inverse(&self, probability: f64) -> f64 {
-        if probability <= 0.0 || probability >= 1.0 {
-            return 0.0;
-        }
-
-        let c0 = 2.515517;
-        let c1 = 0.802853;
-        let c2 = 0.010328;
-        let d1 = 1.432788;
-        let d2 = 0.189269;
-        let d3 = 0.001308;
+        inverse_normal_cdf(probability)
+    }
 
-        let t = if probability > 0.5 {
-            (-2.0 * (1.0 - probability).ln()).sqrt()
-        } else {
-            (-2.0 * probability.ln()).sqrt()
-        };
+    /// Lead time and ordering limits from the supplier catalog and the location item
+    async fn get_replenishment_constraints(
+        &self,
+        product_id: Uuid,
+        location_id: Uuid,
+    ) -> Result<(LeadTime, OrderConstraints)> {
+        use sqlx::Row;
+
+        let catalog = sqlx::query(
+            "SELECT min_order_quantity, pack_size, lead_time_days FROM public.supplier_catalog_entries \
+             WHERE product_id = $1 AND is_active AND valid_from <= NOW() \
+               AND (valid_to IS NULL OR valid_to > NOW()) \
+             ORDER BY lead_time_days, min_order_quantity LIMIT 1",
+        )
+        .bind(product_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| MasterDataError::DatabaseError(e.to_string()))?;
 
-        let numerator = c0 + c1 * t + c2 * t.powi(2);
-        let denominator = 1.0 + d1 * t + d2 * t.powi(2) + d3 * t.powi(3);
-        let z = t - numerator / denominator;
+        let item = sqlx::query(
+            "SELECT lead_time_days, max_stock_level FROM location_items \
+             WHERE product_id = $1 AND location_id = $2 LIMIT 1",
+        )
+        .bind(product_id)
+        .bind(location_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| MasterDataError::DatabaseError(e.to_string()))?;
 
-        if probability > 0.5 { z } else { -z }
+        let lead_time = resolve_lead_time(
+            catalog.as_ref().map(|row| row.get::<i32, _>("lead_time_days")),
+            item.as_ref().map(|row| row.get::<i32, _>("lead_time_days")),
+        );
+        let constraints = OrderConstraints {
+            minimum_order_quantity: catalog.as_ref().map(|row| row.get::<i32, _>("min_order_quantity") as f64),
+            pack_size: catalog.as_ref().map(|row| row.get::<i32, _>("pack_size") as f64),
+            max_stock: item.as_ref().map(|row| row.get::<i32, _>("max_stock_level") as f64),
+        };
+        Ok((lead_time, constraints))
     }
 }
 
@@ -513,33 +623,54 @@ impl InventoryOptimizationEngine for PostgresInventoryOptimizationEngine {
         }
 
         let demand_values: Vec<f64> = historical_data.iter().map(|(_, d)| *d).collect();
-        let average_daily_demand = demand_values.iter().sum::<f64>() / demand_values.len() as f64;
-        let annual_demand = average_daily_demand * 365.0;
-        let demand_variance = self.calculate_variance(&demand_values).await;
-        let demand_std_dev = demand_variance.sqrt();
-
-        let eoq = (2.0 * annual_demand * parameters.ordering_cost / parameters.holding_cost_rate)
-            .sqrt();
-
-        let z_score = self
-            .normal_distribution_inverse(parameters.target_service_level)
-            .await;
-        let lead_time_days = 7.0;
-        let safety_stock = z_score * demand_std_dev * (lead_time_days as f64).sqrt();
-        let reorder_point = average_daily_demand * lead_time_days + safety_stock;
-        let max_stock = reorder_point + eoq;
+        let demand = DemandStatistics::from_daily_demand(365, &demand_values);
+        let (lead_time, constraints) = self
+            .get_replenishment_constraints(product_id, location_id)
+            .await?;
 
-        let holding_cost = (eoq / 2.0 + safety_stock) * parameters.holding_cost_rate;
-        let ordering_cost = annual_demand / eoq * parameters.ordering_cost;
+        let (calculation, constraints_applied) = compute_recommendation(&RecommendationInputs {
+            mean_daily_demand: demand.mean_daily_demand,
+            demand_std_dev: demand.std_dev,
+            lead_time_days: lead_time.days,
+            service_level: parameters.target_service_level,
+            ordering_cost: parameters.ordering_cost,
+            holding_cost_rate: parameters.holding_cost_rate,
+            constraints,
+        });
+
+        let order_quantity = calculation.order_quantity;
+        let holding_cost = (order_quantity / 2.0 + calculation.safety_stock) * parameters.holding_cost_rate;
+        let ordering_cost = if order_quantity > 0.0 {
+            calculation.annual_demand / order_quantity * parameters.ordering_cost
+        } else {
+            0.0
+        };
         let total_cost = holding_cost + ordering_cost;
 
+        let accuracy_window = 30;
+        let explanation = RecommendationExplanation {
+            forecast: ForecastInfo {
+                method: "Exponential smoothing".to_string(),
+                recent_accuracy: one_step_forecast_accuracy(&demand_values, FORECAST_SMOOTHING_ALPHA, accuracy_window),
+                accuracy_window_days: accuracy_window,
+            },
+            demand,
+            lead_time,
+            service_level: parameters.target_service_level,
+            parameter_set_version: parameters.version,
+            ordering_cost: parameters.ordering_cost,
+            holding_cost_rate: parameters.holding_cost_rate,
+            constraints_applied,
+            calculation,
+        };
+
         Ok(OptimizationResult {
             product_id,
             location_id,
-            recommended_reorder_point: reorder_point,
-            recommended_order_quantity: eoq,
-            recommended_safety_stock: safety_stock,
-            recommended_max_stock: max_stock,
+            recommended_reorder_point: explanation.calculation.reorder_point,
+            recommended_order_quantity: order_quantity,
+            recommended_safety_stock: explanation.calculation.safety_stock,
+            recommended_max_stock: explanation.calculation.max_stock,
             expected_service_level: parameters.target_service_level,
             expected_total_cost: total_cost,
             expected_holding_cost: holding_cost,
@@ -549,6 +680,7 @@ impl InventoryOptimizationEngine for PostgresInventoryOptimizationEngine {
             optimization_method: "Economic Order Quantity with Safety Stock".to_string(),
             last_updated: Utc::now(),
             validity_period_days: 30,
+            explanation,
         })
     }
 
@@ -589,6 +721,7 @@ impl InventoryOptimizationEngine for PostgresInventoryOptimizationEngine {
         };
 
         Ok(InventoryOptimizationReport {
+            id: Uuid::new_v4(),
             location_id,
             optimization_date: Utc::now(),
             total_products_analyzed: optimization_results.len(),
@@ -612,7 +745,6 @@ impl InventoryOptimizationEngine for PostgresInventoryOptimizationEngine {
         parameters: &OptimizationParameters,
     ) -> Result<SupplyChainOptimization> {
         let mut recommended_transfers = Vec::new();
-        let mut total_cost_savings = 0.0;
 
         for from_location in &location_ids {
             for to_location in &location_ids {
@@ -653,12 +785,17 @@ impl InventoryOptimizationEngine for PostgresInventoryOptimizationEngine {
                             urgency_level: "Medium".to_string(),
                             reason: "Excess stock rebalancing".to_string(),
                         });
-                        total_cost_savings += transfer_quantity * 1.5;
                     }
                 }
             }
         }
 
+        let recommended_transfers = self.fit_transfers_into_capacity(recommended_transfers).await?;
+        let total_cost_savings: f64 = recommended_transfers
+            .iter()
+            .map(|transfer| transfer.recommended_quantity * 1.5)
+            .sum();
+
         Ok(SupplyChainOptimization {
             optimization_id: Uuid::new_v4(),
             optimization_date: Utc::now(),
@@ -739,6 +876,11 @@ impl InventoryOptimizationEngine for PostgresInventoryOptimizationEngine {
         })
     }
 
+    async fn get_lead_time(&self, product_id: Uuid, location_id: Uuid) -> Result<LeadTime> {
+        let (lead_time, _) = self.get_replenishment_constraints(product_id, location_id).await?;
+        Ok(lead_time)
+    }
+
     async fn calculate_economic_order_quantity(
         &self,
         _product_id: Uuid,
@@ -833,11 +975,11 @@ impl InventoryOptimizationEngine for PostgresInventoryOptimizationEngine {
                         max_stock_level: optimization_result.recommended_max_stock as i32,
                         min_stock_level: optimization_result.recommended_safety_stock as i32,
                         safety_stock: optimization_result.recommended_safety_stock as i32,
-                        lead_time_days: 7,
+                        lead_time_days: optimization_result.explanation.lead_time.days.round() as i32,
                         review_period_days: 30,
                         service_level_target: optimization_result.expected_service_level,
-                        cost_per_order: 50.0,
-                        carrying_cost_rate: 0.25,
+                        cost_per_order: optimization_result.explanation.ordering_cost,
+                        carrying_cost_rate: optimization_result.explanation.holding_cost_rate,
                         automatic_ordering: true,
                         supplier_id: None,
                         preferred_vendor: None,
@@ -0,0 +1,825 @@
+//! # Pick Waves
+//!
+//! A [`PickWave`] groups active stock reservations of one location into a
+//! single round through the warehouse. Reservations are selected by hand or
+//! automatically: every reservation made up to a cutoff time that is not
+//! already part of an open wave.
+//!
+//! Each reservation becomes a [`PickLine`] carrying the zone and bin the
+//! product is stored in. A [`PickPathStrategy`] puts the lines in walking
+//! order; [`SerpentinePath`] goes through the zones in code order and walks
+//! every other zone backwards, so the picker never walks back to the start
+//! of a zone.
+//!
+//! ## Lifecycle
+//!
+//! ```text
+//! open ─► closed
+//! ```
+//!
+//! While the wave is open the picker confirms line by line what was taken
+//! from the bin. Taking less than requested is a short pick: the missing
+//! quantity is re-allocated to another location with enough free stock, or
+//! backordered when there is none. Closing the wave turns every confirmed
+//! line into one outbound shipment movement and fulfils its reservation;
+//! lines never confirmed are released and their reservations stay active for
+//! a later wave.
+
+use crate::error::{MasterDataError, Result};
+use crate::inventory::model::InventoryMovement;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Where a wave is in its lifecycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WaveStatus {
+    /// Being picked
+    Open,
+    /// Picks booked as movements; no further changes
+    Closed,
+}
+
+impl WaveStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            WaveStatus::Open => "open",
+            WaveStatus::Closed => "closed",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "open" => Some(WaveStatus::Open),
+            "closed" => Some(WaveStatus::Closed),
+            _ => None,
+        }
+    }
+}
+
+/// Where a single line of a wave is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PickLineStatus {
+    /// Not confirmed yet
+    Open,
+    /// Confirmed with the full quantity
+    Picked,
+    /// Confirmed with less than requested
+    Short,
+    /// Not confirmed when the wave closed; the reservation stays active
+    Released,
+}
+
+impl PickLineStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PickLineStatus::Open => "open",
+            PickLineStatus::Picked => "picked",
+            PickLineStatus::Short => "short",
+            PickLineStatus::Released => "released",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "open" => Some(PickLineStatus::Open),
+            "picked" => Some(PickLineStatus::Picked),
+            "short" => Some(PickLineStatus::Short),
+            "released" => Some(PickLineStatus::Released),
+            _ => None,
+        }
+    }
+
+    pub fn is_confirmed(self) -> bool {
+        matches!(self, PickLineStatus::Picked | PickLineStatus::Short)
+    }
+}
+
+/// What happened to the missing quantity of a short pick
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShortPickResolution {
+    /// Reserved at another location instead
+    Reallocated,
+    /// No location had enough free stock; the order waits for supply
+    Backordered,
+}
+
+impl ShortPickResolution {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ShortPickResolution::Reallocated => "reallocated",
+            ShortPickResolution::Backordered => "backordered",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "reallocated" => Some(ShortPickResolution::Reallocated),
+            "backordered" => Some(ShortPickResolution::Backordered),
+            _ => None,
+        }
+    }
+}
+
+/// Which reservations go into a new wave
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum WaveSelection {
+    /// Exactly these reservations
+    Manual { reservation_ids: Vec<Uuid> },
+    /// Every reservation made up to the cutoff that is not in an open wave
+    Cutoff { cutoff_at: DateTime<Utc> },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreatePickWaveRequest {
+    pub location_id: Uuid,
+    pub selection: WaveSelection,
+    /// Picker who sees the wave in their pick list
+    pub assigned_to: Option<Uuid>,
+}
+
+/// What the picker took from the bin
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfirmPickRequest {
+    pub picked_quantity: i32,
+    /// Why less than requested was picked
+    pub reason: Option<String>,
+}
+
+/// An active reservation that can be picked
+#[derive(Debug, Clone, PartialEq)]
+pub struct PickCandidate {
+    pub reservation_id: Uuid,
+    pub product_id: Uuid,
+    pub location_item_id: Uuid,
+    pub zone_code: Option<String>,
+    pub bin_code: Option<String>,
+    pub reference_number: Option<String>,
+    pub quantity: i32,
+    pub reserved_at: DateTime<Utc>,
+}
+
+/// Free stock of a product at another location, considered for re-allocation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StockOption {
+    pub location_id: Uuid,
+    pub location_item_id: Uuid,
+    /// Available minus reserved
+    pub free_quantity: i32,
+}
+
+/// A new reservation covering the missing quantity of a short pick
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Reallocation {
+    pub reservation_id: Uuid,
+    /// Reservation the short pick belonged to; the new one copies its reference
+    pub original_reservation_id: Uuid,
+    pub location_id: Uuid,
+    pub location_item_id: Uuid,
+    pub quantity: i32,
+}
+
+/// One reservation to pick, in path order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PickLine {
+    pub id: Uuid,
+    /// Position on the pick path, starting at 1
+    pub sequence: i32,
+    pub reservation_id: Uuid,
+    pub product_id: Uuid,
+    pub location_item_id: Uuid,
+    pub zone_code: Option<String>,
+    pub bin_code: Option<String>,
+    pub reference_number: Option<String>,
+    pub requested_quantity: i32,
+    pub picked_quantity: Option<i32>,
+    pub status: PickLineStatus,
+    pub short_reason: Option<String>,
+    pub short_resolution: Option<ShortPickResolution>,
+    pub reallocated_location_id: Option<Uuid>,
+    pub reallocated_reservation_id: Option<Uuid>,
+    pub confirmed_by: Option<Uuid>,
+    pub confirmed_at: Option<DateTime<Utc>>,
+}
+
+impl PickLine {
+    /// Requested but not picked; zero until confirmed short
+    pub fn shortage(&self) -> i32 {
+        match (self.status, self.picked_quantity) {
+            (PickLineStatus::Short, Some(picked)) => self.requested_quantity - picked,
+            _ => 0,
+        }
+    }
+}
+
+/// A round through one location
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PickWave {
+    pub id: Uuid,
+    pub tenant_id: Uuid,
+    pub wave_number: String,
+    pub location_id: Uuid,
+    pub status: WaveStatus,
+    /// Name of the [`PickPathStrategy`] that ordered the lines
+    pub path_strategy: String,
+    /// Set for waves selected by cutoff time
+    pub cutoff_at: Option<DateTime<Utc>>,
+    pub assigned_to: Option<Uuid>,
+    pub lines: Vec<PickLine>,
+    pub created_by: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub closed_by: Option<Uuid>,
+    pub closed_at: Option<DateTime<Utc>>,
+}
+
+/// A reservation fulfilled by closing a wave
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FulfilledReservation {
+    pub reservation_id: Uuid,
+    pub location_item_id: Uuid,
+    /// Reserved quantity given up: the picked units leave, the shortage was re-allocated or backordered
+    pub released_quantity: i32,
+    pub picked_quantity: i32,
+}
+
+/// Stock changes of a closing wave, applied together
+#[derive(Debug, Clone, Default)]
+pub struct WaveClosing {
+    /// One outbound shipment per line with picked units
+    pub movements: Vec<InventoryMovement>,
+    pub fulfilled: Vec<FulfilledReservation>,
+}
+
+/// A short-picked line, as shown in the wave summary
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PickException {
+    pub line_id: Uuid,
+    pub sequence: i32,
+    pub product_id: Uuid,
+    pub bin_code: Option<String>,
+    pub requested_quantity: i32,
+    pub picked_quantity: i32,
+    pub reason: Option<String>,
+    pub resolution: Option<ShortPickResolution>,
+    pub reallocated_location_id: Option<Uuid>,
+}
+
+/// Progress of a wave
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WaveSummary {
+    pub wave_id: Uuid,
+    pub wave_number: String,
+    pub status: WaveStatus,
+    pub total_lines: i32,
+    pub confirmed_lines: i32,
+    pub released_lines: i32,
+    /// Confirmed lines out of all lines, 0–100
+    pub completion_percentage: f64,
+    pub requested_quantity: i32,
+    pub picked_quantity: i32,
+    pub exceptions: Vec<PickException>,
+}
+
+/// Orders the lines of a wave into a walking path
+pub trait PickPathStrategy: Send + Sync {
+    /// Stored on the wave so the order can be explained later
+    fn name(&self) -> &'static str;
+
+    /// Sort the lines into picking order
+    fn order(&self, lines: &mut [PickLine]);
+}
+
+/// Zones in code order, bins ascending in the first zone, descending in the
+/// second and so on. Lines without a zone come last, and lines without a bin
+/// last within their zone; ties keep their reservation order.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SerpentinePath;
+
+impl PickPathStrategy for SerpentinePath {
+    fn name(&self) -> &'static str {
+        "serpentine"
+    }
+
+    fn order(&self, lines: &mut [PickLine]) {
+        let mut zones: Vec<&str> = lines.iter().filter_map(|line| line.zone_code.as_deref()).collect();
+        zones.sort_by(|a, b| natural_cmp(a, b));
+        zones.dedup();
+        let zone_rank: HashMap<String, usize> = zones
+            .iter()
+            .enumerate()
+            .map(|(rank, zone)| (zone.to_string(), rank))
+            .collect();
+
+        let rank = |line: &PickLine| {
+            line.zone_code
+                .as_ref()
+                .and_then(|zone| zone_rank.get(zone).copied())
+                .unwrap_or(usize::MAX)
+        };
+        lines.sort_by(|a, b| {
+            let (rank_a, rank_b) = (rank(a), rank(b));
+            rank_a.cmp(&rank_b).then_with(|| match (&a.bin_code, &b.bin_code) {
+                (Some(bin_a), Some(bin_b)) if rank_a % 2 == 1 && rank_a != usize::MAX => natural_cmp(bin_b, bin_a),
+                (Some(bin_a), Some(bin_b)) => natural_cmp(bin_a, bin_b),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            })
+        });
+    }
+}
+
+/// Compare codes so that `A-9` sorts before `A-10`
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a.chars().peekable(), b.chars().peekable());
+    loop {
+        match (a.peek().copied(), b.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let number = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+                    let mut digits = String::new();
+                    while let Some(c) = chars.peek().copied().filter(char::is_ascii_digit) {
+                        digits.push(c);
+                        chars.next();
+                    }
+                    digits.trim_start_matches('0').to_string()
+                };
+                let (x, y) = (number(&mut a), number(&mut b));
+                let ordering = x.len().cmp(&y.len()).then_with(|| x.cmp(&y));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(x), Some(y)) => {
+                let ordering = x.to_ascii_uppercase().cmp(&y.to_ascii_uppercase());
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+                a.next();
+                b.next();
+            }
+        }
+    }
+}
+
+impl PickWave {
+    /// Build a wave from the candidates found for the request, lines in path order
+    pub fn new(
+        tenant_id: Uuid,
+        request: &CreatePickWaveRequest,
+        candidates: Vec<PickCandidate>,
+        strategy: &dyn PickPathStrategy,
+        created_by: Uuid,
+        now: DateTime<Utc>,
+    ) -> Result<Self> {
+        if let WaveSelection::Manual { reservation_ids } = &request.selection {
+            if reservation_ids.is_empty() {
+                return Err(validation("selection", "Select at least one reservation"));
+            }
+            if let Some(missing) = reservation_ids
+                .iter()
+                .find(|id| !candidates.iter().any(|candidate| candidate.reservation_id == **id))
+            {
+                return Err(validation(
+                    "selection",
+                    &format!(
+                        "Reservation {} is not active at this location or is already in an open wave",
+                        missing
+                    ),
+                ));
+            }
+        }
+        if candidates.is_empty() {
+            return Err(validation("selection", "No open reservations to pick at this location"));
+        }
+
+        let mut lines: Vec<PickLine> = candidates
+            .into_iter()
+            .map(|candidate| PickLine {
+                id: Uuid::new_v4(),
+                sequence: 0,
+                reservation_id: candidate.reservation_id,
+                product_id: candidate.product_id,
+                location_item_id: candidate.location_item_id,
+                zone_code: candidate.zone_code,
+                bin_code: candidate.bin_code,
+                reference_number: candidate.reference_number,
+                requested_quantity: candidate.quantity,
+                picked_quantity: None,
+                status: PickLineStatus::Open,
+                short_reason: None,
+                short_resolution: None,
+                reallocated_location_id: None,
+                reallocated_reservation_id: None,
+                confirmed_by: None,
+                confirmed_at: None,
+            })
+            .collect();
+        strategy.order(&mut lines);
+        for (index, line) in lines.iter_mut().enumerate() {
+            line.sequence = index as i32 + 1;
+        }
+
+        let id = Uuid::new_v4();
+        Ok(Self {
+            id,
+            tenant_id,
+            wave_number: wave_number(id, now),
+            location_id: request.location_id,
+            status: WaveStatus::Open,
+            path_strategy: strategy.name().to_string(),
+            cutoff_at: match request.selection {
+                WaveSelection::Cutoff { cutoff_at } => Some(cutoff_at),
+                WaveSelection::Manual { .. } => None,
+            },
+            assigned_to: request.assigned_to,
+            lines,
+            created_by,
+            created_at: now,
+            updated_at: now,
+            closed_by: None,
+            closed_at: None,
+        })
+    }
+
+    fn ensure_open(&self, to: WaveStatus) -> Result<()> {
+        if self.status == WaveStatus::Open {
+            Ok(())
+        } else {
+            Err(MasterDataError::InvalidWaveTransition {
+                wave_number: self.wave_number.clone(),
+                from: self.status.as_str().to_string(),
+                to: to.as_str().to_string(),
+            })
+        }
+    }
+
+    fn line_mut(&mut self, line_id: Uuid) -> Result<&mut PickLine> {
+        self.lines
+            .iter_mut()
+            .find(|line| line.id == line_id)
+            .ok_or_else(|| MasterDataError::NotFoundError(format!("Pick line {}", line_id)))
+    }
+
+    /// Record what was picked for one line. Returns the shortage, which the
+    /// caller resolves with [`PickWave::resolve_short_pick`].
+    pub fn confirm_line(
+        &mut self,
+        line_id: Uuid,
+        request: &ConfirmPickRequest,
+        confirmed_by: Uuid,
+        now: DateTime<Utc>,
+    ) -> Result<i32> {
+        self.ensure_open(WaveStatus::Open)?;
+        let line = self.line_mut(line_id)?;
+        if line.status != PickLineStatus::Open {
+            return Err(validation("line_id", &format!("Line {} is already confirmed", line.sequence)));
+        }
+        if request.picked_quantity < 0 || request.picked_quantity > line.requested_quantity {
+            return Err(validation(
+                "picked_quantity",
+                &format!("Picked quantity must be between 0 and {}", line.requested_quantity),
+            ));
+        }
+
+        line.picked_quantity = Some(request.picked_quantity);
+        line.confirmed_by = Some(confirmed_by);
+        line.confirmed_at = Some(now);
+        if request.picked_quantity < line.requested_quantity {
+            line.status = PickLineStatus::Short;
+            line.short_reason = request.reason.clone();
+        } else {
+            line.status = PickLineStatus::Picked;
+        }
+        let shortage = line.shortage();
+        self.updated_at = now;
+        Ok(shortage)
+    }
+
+    /// Re-allocate the shortage of a short line to the location with the most
+    /// free stock that covers it, or backorder it when none does
+    pub fn resolve_short_pick(&mut self, line_id: Uuid, options: &[StockOption]) -> Result<Option<Reallocation>> {
+        let location_id = self.location_id;
+        let line = self.line_mut(line_id)?;
+        let shortage = line.shortage();
+        if shortage == 0 {
+            return Ok(None);
+        }
+
+        let best = options
+            .iter()
+            .filter(|option| option.location_id != location_id && option.free_quantity >= shortage)
+            .max_by(|a, b| {
+                a.free_quantity
+                    .cmp(&b.free_quantity)
+                    .then_with(|| b.location_id.cmp(&a.location_id))
+            });
+
+        match best {
+            Some(option) => {
+                let reallocation = Reallocation {
+                    reservation_id: Uuid::new_v4(),
+                    original_reservation_id: line.reservation_id,
+                    location_id: option.location_id,
+                    location_item_id: option.location_item_id,
+                    quantity: shortage,
+                };
+                line.short_resolution = Some(ShortPickResolution::Reallocated);
+                line.reallocated_location_id = Some(option.location_id);
+                line.reallocated_reservation_id = Some(reallocation.reservation_id);
+                Ok(Some(reallocation))
+            }
+            None => {
+                line.short_resolution = Some(ShortPickResolution::Backordered);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Close the wave: confirmed lines become shipments and fulfil their
+    /// reservations, open lines are released
+    pub fn close(&mut self, closed_by: Uuid, now: DateTime<Utc>) -> Result<WaveClosing> {
+        self.ensure_open(WaveStatus::Closed)?;
+
+        let mut closing = WaveClosing::default();
+        for line in &mut self.lines {
+            if !line.status.is_confirmed() {
+                line.status = PickLineStatus::Released;
+                continue;
+            }
+            let picked = line.picked_quantity.unwrap_or(0);
+            closing.fulfilled.push(FulfilledReservation {
+                reservation_id: line.reservation_id,
+                location_item_id: line.location_item_id,
+                released_quantity: line.requested_quantity,
+                picked_quantity: picked,
+            });
+            if picked > 0 {
+                closing.movements.push(InventoryMovement {
+                    id: Some(Uuid::new_v4()),
+                    product_id: Some(line.product_id),
+                    location_id: Some(self.location_id),
+                    movement_type: Some("shipment".to_string()),
+                    quantity: Some(-picked),
+                    unit_cost: None,
+                    reference_document: Some("pick_wave".to_string()),
+                    reference_number: Some(self.wave_number.clone()),
+                    reason: Some("picked".to_string()),
+                    batch_number: None,
+                    serial_numbers: None,
+                    expiry_date: None,
+                    operator_id: line.confirmed_by.or(Some(closed_by)),
+                    operator_name: None,
+                    created_at: Some(now),
+                    effective_date: line.confirmed_at.or(Some(now)),
+                    audit_trail: None,
+                });
+            }
+        }
+
+        self.status = WaveStatus::Closed;
+        self.closed_by = Some(closed_by);
+        self.closed_at = Some(now);
+        self.updated_at = now;
+        Ok(closing)
+    }
+
+    pub fn summary(&self) -> WaveSummary {
+        let total_lines = self.lines.len() as i32;
+        let confirmed_lines = self.lines.iter().filter(|line| line.status.is_confirmed()).count() as i32;
+        let completion_percentage = if total_lines == 0 {
+            0.0
+        } else {
+            (confirmed_lines as f64 * 1000.0 / total_lines as f64).round() / 10.0
+        };
+
+        WaveSummary {
+            wave_id: self.id,
+            wave_number: self.wave_number.clone(),
+            status: self.status,
+            total_lines,
+            confirmed_lines,
+            released_lines: self.lines.iter().filter(|line| line.status == PickLineStatus::Released).count() as i32,
+            completion_percentage,
+            requested_quantity: self.lines.iter().map(|line| line.requested_quantity).sum(),
+            picked_quantity: self.lines.iter().filter_map(|line| line.picked_quantity).sum(),
+            exceptions: self
+                .lines
+                .iter()
+                .filter(|line| line.status == PickLineStatus::Short)
+                .map(|line| PickException {
+                    line_id: line.id,
+                    sequence: line.sequence,
+                    product_id: line.product_id,
+                    bin_code: line.bin_code.clone(),
+                    requested_quantity: line.requested_quantity,
+                    picked_quantity: line.picked_quantity.unwrap_or(0),
+                    reason: line.short_reason.clone(),
+                    resolution: line.short_resolution,
+                    reallocated_location_id: line.reallocated_location_id,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// `WAVE-YYYYMMDD-` plus the first eight hex digits of the wave id
+fn wave_number(id: Uuid, now: DateTime<Utc>) -> String {
+    format!("WAVE-{}-{}", now.format("%Y%m%d"), &id.simple().to_string()[..8].to_uppercase())
+}
+
+fn validation(field: &str, message: &str) -> MasterDataError {
+    MasterDataError::ValidationError {
+        field: field.to_string(),
+        message: message.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(zone: Option<&str>, bin: Option<&str>, quantity: i32) -> PickCandidate {
+        PickCandidate {
+            reservation_id: Uuid::new_v4(),
+            product_id: Uuid::new_v4(),
+            location_item_id: Uuid::new_v4(),
+            zone_code: zone.map(str::to_string),
+            bin_code: bin.map(str::to_string),
+            reference_number: Some("SO-1".to_string()),
+            quantity,
+            reserved_at: Utc::now(),
+        }
+    }
+
+    fn wave(candidates: Vec<PickCandidate>) -> PickWave {
+        let request = CreatePickWaveRequest {
+            location_id: Uuid::new_v4(),
+            selection: WaveSelection::Cutoff { cutoff_at: Utc::now() },
+            assigned_to: None,
+        };
+        PickWave::new(Uuid::new_v4(), &request, candidates, &SerpentinePath, Uuid::new_v4(), Utc::now()).unwrap()
+    }
+
+    fn path(wave: &PickWave) -> Vec<String> {
+        wave.lines
+            .iter()
+            .map(|line| format!("{}/{}", line.zone_code.as_deref().unwrap_or("-"), line.bin_code.as_deref().unwrap_or("-")))
+            .collect()
+    }
+
+    fn confirm(wave: &mut PickWave, index: usize, picked: i32) -> i32 {
+        let line_id = wave.lines[index].id;
+        let request = ConfirmPickRequest { picked_quantity: picked, reason: Some("bin empty".to_string()) };
+        wave.confirm_line(line_id, &request, Uuid::new_v4(), Utc::now()).unwrap()
+    }
+
+    #[test]
+    fn test_serpentine_path_ordering() {
+        let wave = wave(vec![
+            candidate(Some("B"), Some("B-02"), 1),
+            candidate(None, Some("X-01"), 1),
+            candidate(Some("A"), Some("A-10"), 1),
+            candidate(Some("C"), None, 1),
+            candidate(Some("B"), Some("B-10"), 1),
+            candidate(Some("A"), Some("A-9"), 1),
+            candidate(Some("C"), Some("C-01"), 1),
+            candidate(Some("A"), None, 1),
+        ]);
+
+        assert_eq!(
+            path(&wave),
+            vec!["A/A-9", "A/A-10", "A/-", "B/B-10", "B/B-02", "C/C-01", "C/-", "-/X-01"]
+        );
+        assert_eq!(wave.lines.iter().map(|line| line.sequence).collect::<Vec<_>>(), (1..=8).collect::<Vec<_>>());
+        assert_eq!(wave.path_strategy, "serpentine");
+        assert!(wave.wave_number.starts_with("WAVE-"));
+    }
+
+    #[test]
+    fn test_manual_selection_must_be_available() {
+        let available = candidate(Some("A"), Some("A-01"), 2);
+        let request = |reservation_ids| CreatePickWaveRequest {
+            location_id: Uuid::new_v4(),
+            selection: WaveSelection::Manual { reservation_ids },
+            assigned_to: None,
+        };
+        let create = |request: CreatePickWaveRequest, candidates| {
+            PickWave::new(Uuid::new_v4(), &request, candidates, &SerpentinePath, Uuid::new_v4(), Utc::now())
+        };
+
+        assert!(create(request(vec![]), vec![]).is_err());
+        // A reservation already in another wave is not among the candidates
+        assert!(create(request(vec![available.reservation_id, Uuid::new_v4()]), vec![available.clone()]).is_err());
+
+        let wave = create(request(vec![available.reservation_id]), vec![available]).unwrap();
+        assert_eq!(wave.lines.len(), 1);
+        assert_eq!(wave.cutoff_at, None);
+    }
+
+    #[test]
+    fn test_confirm_validates_quantity_and_state() {
+        let mut wave = wave(vec![candidate(Some("A"), Some("A-01"), 3)]);
+        let line_id = wave.lines[0].id;
+        let confirm_with = |wave: &mut PickWave, picked| {
+            let request = ConfirmPickRequest { picked_quantity: picked, reason: None };
+            wave.confirm_line(line_id, &request, Uuid::new_v4(), Utc::now())
+        };
+
+        assert!(confirm_with(&mut wave, 4).is_err());
+        assert!(confirm_with(&mut wave, -1).is_err());
+        assert_eq!(confirm_with(&mut wave, 3).unwrap(), 0);
+        assert_eq!(wave.lines[0].status, PickLineStatus::Picked);
+        assert!(confirm_with(&mut wave, 3).is_err());
+
+        wave.close(Uuid::new_v4(), Utc::now()).unwrap();
+        assert!(matches!(
+            wave.close(Uuid::new_v4(), Utc::now()),
+            Err(MasterDataError::InvalidWaveTransition { .. })
+        ));
+    }
+
+    #[test]
+    fn test_short_pick_reallocates_or_backorders() {
+        let mut wave = wave(vec![candidate(Some("A"), Some("A-01"), 5), candidate(Some("A"), Some("A-02"), 4)]);
+        let (first, second) = (wave.lines[0].id, wave.lines[1].id);
+
+        assert_eq!(confirm(&mut wave, 0, 2), 3);
+        let own_location = StockOption { location_id: wave.location_id, location_item_id: Uuid::new_v4(), free_quantity: 50 };
+        let too_small = StockOption { location_id: Uuid::new_v4(), location_item_id: Uuid::new_v4(), free_quantity: 2 };
+        let enough = StockOption { location_id: Uuid::new_v4(), location_item_id: Uuid::new_v4(), free_quantity: 3 };
+        let plenty = StockOption { location_id: Uuid::new_v4(), location_item_id: Uuid::new_v4(), free_quantity: 8 };
+
+        let reallocation = wave
+            .resolve_short_pick(first, &[own_location, too_small, enough, plenty])
+            .unwrap()
+            .expect("a location covers the shortage");
+        assert_eq!(reallocation.location_id, plenty.location_id);
+        assert_eq!(reallocation.quantity, 3);
+        assert_eq!(reallocation.original_reservation_id, wave.lines[0].reservation_id);
+        assert_eq!(wave.lines[0].short_resolution, Some(ShortPickResolution::Reallocated));
+        assert_eq!(wave.lines[0].reallocated_reservation_id, Some(reallocation.reservation_id));
+
+        assert_eq!(confirm(&mut wave, 1, 0), 4);
+        assert_eq!(wave.resolve_short_pick(second, &[too_small, enough]).unwrap(), None);
+        assert_eq!(wave.lines[1].short_resolution, Some(ShortPickResolution::Backordered));
+
+        let summary = wave.summary();
+        assert_eq!(summary.completion_percentage, 100.0);
+        assert_eq!((summary.requested_quantity, summary.picked_quantity), (9, 2));
+        assert_eq!(
+            summary.exceptions.iter().map(|e| (e.sequence, e.resolution)).collect::<Vec<_>>(),
+            vec![(1, Some(ShortPickResolution::Reallocated)), (2, Some(ShortPickResolution::Backordered))]
+        );
+    }
+
+    #[test]
+    fn test_close_books_exactly_the_confirmed_picks() {
+        let mut wave = wave(vec![
+            candidate(Some("A"), Some("A-01"), 4),
+            candidate(Some("A"), Some("A-02"), 2),
+            candidate(Some("A"), Some("A-03"), 6),
+            candidate(Some("A"), Some("A-04"), 1),
+        ]);
+        confirm(&mut wave, 0, 4);
+        confirm(&mut wave, 1, 0);
+        confirm(&mut wave, 2, 5);
+        assert_eq!(wave.summary().completion_percentage, 75.0);
+
+        let closing = wave.close(Uuid::new_v4(), Utc::now()).unwrap();
+
+        let booked: Vec<(Uuid, &str, i32)> = closing
+            .movements
+            .iter()
+            .map(|m| (m.product_id.unwrap(), m.movement_type.as_deref().unwrap(), m.quantity.unwrap()))
+            .collect();
+        assert_eq!(
+            booked,
+            vec![(wave.lines[0].product_id, "shipment", -4), (wave.lines[2].product_id, "shipment", -5)]
+        );
+        assert!(closing.movements.iter().all(|m| m.location_id == Some(wave.location_id)
+            && m.reference_number.as_deref() == Some(wave.wave_number.as_str())));
+
+        // The zero pick still fulfils its reservation; the unconfirmed line keeps it
+        assert_eq!(
+            closing.fulfilled.iter().map(|f| (f.reservation_id, f.released_quantity, f.picked_quantity)).collect::<Vec<_>>(),
+            vec![
+                (wave.lines[0].reservation_id, 4, 4),
+                (wave.lines[1].reservation_id, 2, 0),
+                (wave.lines[2].reservation_id, 6, 5),
+            ]
+        );
+        assert_eq!(wave.lines[3].status, PickLineStatus::Released);
+        assert_eq!(wave.status, WaveStatus::Closed);
+        assert_eq!(wave.summary().released_lines, 1);
+    }
+}
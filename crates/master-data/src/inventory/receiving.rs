@@ -0,0 +1,938 @@
+//! # Purchase Order Receipts
+//!
+//! Receiving books delivered units of a purchase order into the order's
+//! location, line by line. A receipt may cover part of an order; lines are
+//! filled in order until each reaches its ordered quantity. Beyond that a
+//! line takes at most `over_receipt_tolerance_percent` of its ordered
+//! quantity more (see [`ReceivingSettings`]); anything above is refused.
+//!
+//! ## Line and order status
+//!
+//! ```text
+//! open ─► partially_received ─► received
+//!   └──────────┴──────────────► closed_short (with a reason)
+//! cancelled
+//! ```
+//!
+//! A receipt line naming its order line may close it short: the units not
+//! delivered are no longer expected and the reason is kept. Received,
+//! closed-short and cancelled lines take no further receipts. The order is
+//! `partially_received` until every line is closed, then `received`, or
+//! `closed_short` if any of its lines was.
+//!
+//! ## Batches, serials and inspections
+//!
+//! Before the stock is booked, the receipt is checked against the capacity
+//! of the location (see [`capacity`](super::capacity)); a receipt that would
+//! overfill it needs an override.
+//!
+//! Each line's units are booked into a batch: the batch number given on the
+//! line, or the order number. Products with `batch_tracking_required` need
+//! the batch number given, serialized products one serial number per unit;
+//! batches held back after a failed inspection take no new units. Batches of
+//! products that need inspection get their incoming-goods inspection
+//! scheduled right after the receipt (see
+//! [`quality`](crate::product::quality)).
+//!
+//! Line quantities and statuses, movements, batches, serial units and the
+//! [`ReceiptEvent`]s recording who received what and when are written in
+//! one transaction; a receipt failing any of it books nothing. The events
+//! make up the order's receipt history.
+
+use crate::error::{MasterDataError, Result};
+use crate::inventory::capacity::IncomingQuantity;
+use crate::inventory::model::InventoryMovement;
+use crate::inventory::serial::{SerialEvent, SerialEventContext, SerialUnit};
+use crate::product::quality::QualityInspection;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use uuid::Uuid;
+
+/// Purchase order statuses that still expect deliveries
+pub const RECEIVABLE_STATUSES: [&str; 5] = ["submitted", "approved", "ordered", "partially_received", "pending"];
+
+/// How much a delivery may exceed the ordered quantities
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ReceivingSettings {
+    /// Share of a line's ordered quantity that may be received on top of it
+    pub over_receipt_tolerance_percent: f64,
+}
+
+impl ReceivingSettings {
+    /// Units a line may be received beyond its ordered quantity
+    pub fn allowance(&self, quantity_ordered: i32) -> i32 {
+        (quantity_ordered as f64 * self.over_receipt_tolerance_percent.max(0.0) / 100.0).floor() as i32
+    }
+}
+
+/// Where a purchase order line is in receiving
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LineStatus {
+    Open,
+    PartiallyReceived,
+    Received,
+    ClosedShort,
+    Cancelled,
+}
+
+impl LineStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LineStatus::Open => "open",
+            LineStatus::PartiallyReceived => "partially_received",
+            LineStatus::Received => "received",
+            LineStatus::ClosedShort => "closed_short",
+            LineStatus::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "open" => Some(LineStatus::Open),
+            "partially_received" => Some(LineStatus::PartiallyReceived),
+            "received" => Some(LineStatus::Received),
+            "closed_short" => Some(LineStatus::ClosedShort),
+            "cancelled" => Some(LineStatus::Cancelled),
+            _ => None,
+        }
+    }
+
+    /// Whether the line takes no further receipts
+    pub fn is_closed(self) -> bool {
+        matches!(self, LineStatus::Received | LineStatus::ClosedShort | LineStatus::Cancelled)
+    }
+}
+
+/// What receiving needs to know about a purchase order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReceivableOrder {
+    pub id: Uuid,
+    pub order_number: String,
+    pub location_id: Uuid,
+    pub status: String,
+    pub lines: Vec<ReceivableLine>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReceivableLine {
+    pub line_id: Uuid,
+    pub product_id: Uuid,
+    pub quantity_ordered: i32,
+    pub quantity_received: i32,
+    pub status: LineStatus,
+    /// The product needs a batch number on every receipt
+    pub batch_required: bool,
+    /// The product needs a serial number per received unit
+    pub serialized: bool,
+}
+
+impl ReceivableLine {
+    pub fn open_quantity(&self) -> i32 {
+        (self.quantity_ordered - self.quantity_received).max(0)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReceivePurchaseOrderRequest {
+    pub lines: Vec<ReceiptLineRequest>,
+    /// Store the units even if the location or bin ends up over capacity;
+    /// requires `inventory:capacity_override`
+    #[serde(default)]
+    pub override_capacity: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReceiptLineRequest {
+    pub product_id: Uuid,
+    /// Zero only when closing the line short without a further delivery
+    pub quantity: i32,
+    /// Order line the units are for; without it they fill the product's open lines in order
+    #[serde(default)]
+    pub line_id: Option<Uuid>,
+    /// Bin the units are put away in; defaults to the product's current bin
+    pub bin_code: Option<String>,
+    /// Supplier's batch or lot number; defaults to the order number
+    #[serde(default)]
+    pub batch_number: Option<String>,
+    /// One per unit, for serialized products
+    #[serde(default)]
+    pub serial_numbers: Vec<String>,
+    /// Close the line named by `line_id` short after this receipt, for this reason
+    #[serde(default)]
+    pub close_short_reason: Option<String>,
+}
+
+/// Units booked against one purchase order line
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReceiptLine {
+    pub line_id: Uuid,
+    pub product_id: Uuid,
+    pub quantity: i32,
+    pub bin_code: Option<String>,
+    pub batch_number: Option<String>,
+    #[serde(default)]
+    pub serial_numbers: Vec<String>,
+    /// Set when the line is closed short with this receipt
+    #[serde(default)]
+    pub close_short_reason: Option<String>,
+}
+
+/// How a receipt changes one order line
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineUpdate {
+    pub line_id: Uuid,
+    /// Quantity and status the line had when the receipt was planned; the
+    /// update fails if either changed in the meantime
+    pub quantity_received_before: i32,
+    pub status_before: LineStatus,
+    pub quantity_received: i32,
+    pub status: LineStatus,
+    pub close_short_reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReceiptEventKind {
+    Received,
+    ClosedShort,
+}
+
+impl ReceiptEventKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ReceiptEventKind::Received => "received",
+            ReceiptEventKind::ClosedShort => "closed_short",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "received" => Some(ReceiptEventKind::Received),
+            "closed_short" => Some(ReceiptEventKind::ClosedShort),
+            _ => None,
+        }
+    }
+}
+
+/// One entry in a purchase order line's receipt history
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReceiptEvent {
+    pub id: Uuid,
+    /// Shared by all events of one receipt
+    pub receipt_id: Uuid,
+    pub purchase_order_id: Uuid,
+    pub line_id: Uuid,
+    pub product_id: Uuid,
+    pub kind: ReceiptEventKind,
+    /// Units received, or units no longer expected when closed short
+    pub quantity: i32,
+    pub bin_code: Option<String>,
+    pub batch_number: Option<String>,
+    pub serial_numbers: Vec<String>,
+    pub movement_id: Option<Uuid>,
+    pub reason: Option<String>,
+    /// Status of the line after the receipt
+    pub line_status: LineStatus,
+    pub received_by: Uuid,
+    pub received_at: DateTime<Utc>,
+}
+
+/// Everything a receipt writes, in one transaction
+#[derive(Debug, Clone)]
+pub struct ReceiptBooking {
+    pub receipt_id: Uuid,
+    /// Status of the order once the receipt is booked
+    pub status: &'static str,
+    pub lines: Vec<ReceiptLine>,
+    pub updates: Vec<LineUpdate>,
+    pub movements: Vec<InventoryMovement>,
+    pub serial_units: Vec<SerialUnit>,
+    pub serial_events: Vec<SerialEvent>,
+    pub events: Vec<ReceiptEvent>,
+}
+
+/// Outcome of a receipt
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PurchaseOrderReceipt {
+    pub receipt_id: Uuid,
+    pub purchase_order_id: Uuid,
+    pub location_id: Uuid,
+    pub status: String,
+    pub lines: Vec<ReceiptLine>,
+    pub events: Vec<ReceiptEvent>,
+    pub received_by: Uuid,
+    pub received_at: DateTime<Utc>,
+    /// Incoming-goods inspections scheduled for the received batches
+    #[serde(default)]
+    pub inspections: Vec<QualityInspection>,
+}
+
+/// Receipt history of a purchase order, per line
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReceiptHistory {
+    pub purchase_order_id: Uuid,
+    pub order_number: String,
+    pub status: String,
+    pub lines: Vec<LineReceiptHistory>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LineReceiptHistory {
+    pub line_id: Uuid,
+    pub product_id: Uuid,
+    pub quantity_ordered: i32,
+    pub quantity_received: i32,
+    pub status: LineStatus,
+    /// Oldest first
+    pub events: Vec<ReceiptEvent>,
+}
+
+fn invalid(field: &str, message: String) -> MasterDataError {
+    MasterDataError::ValidationError {
+        field: field.to_string(),
+        message,
+    }
+}
+
+fn given(value: &Option<String>) -> Option<&str> {
+    value.as_deref().map(str::trim).filter(|value| !value.is_empty())
+}
+
+/// Spread the requested quantities over the open order lines, refusing the
+/// whole receipt if any of its lines is invalid
+pub fn plan_receipt(
+    order: &ReceivableOrder,
+    request: &ReceivePurchaseOrderRequest,
+    settings: &ReceivingSettings,
+) -> Result<Vec<ReceiptLine>> {
+    if !RECEIVABLE_STATUSES.contains(&order.status.as_str()) {
+        return Err(invalid(
+            "status",
+            format!("Purchase order {} is {} and cannot be received", order.order_number, order.status),
+        ));
+    }
+    if request.lines.is_empty() {
+        return Err(invalid("lines", "A receipt needs at least one line".to_string()));
+    }
+
+    let mut received: Vec<i32> = order.lines.iter().map(|line| line.quantity_received).collect();
+    let mut serials = HashSet::new();
+    let mut receipt = Vec::new();
+    for requested in &request.lines {
+        let closing = given(&requested.close_short_reason);
+        if requested.quantity < 0 || (requested.quantity == 0 && closing.is_none()) {
+            return Err(invalid("quantity", "Received quantity must be positive".to_string()));
+        }
+        if closing.is_some() && requested.line_id.is_none() {
+            return Err(invalid("line_id", "Closing a line short needs the line".to_string()));
+        }
+        if requested.close_short_reason.is_some() && closing.is_none() {
+            return Err(invalid("close_short_reason", "Closing a line short needs a reason".to_string()));
+        }
+
+        let candidates: Vec<usize> = match requested.line_id {
+            Some(line_id) => {
+                let index = order
+                    .lines
+                    .iter()
+                    .position(|line| line.line_id == line_id)
+                    .ok_or_else(|| invalid("line_id", format!("Line {} is not on purchase order {}", line_id, order.order_number)))?;
+                if order.lines[index].product_id != requested.product_id {
+                    return Err(invalid("product_id", format!("Line {} is for another product", line_id)));
+                }
+                vec![index]
+            }
+            None => (0..order.lines.len()).filter(|&i| order.lines[i].product_id == requested.product_id).collect(),
+        };
+        if candidates.is_empty() {
+            return Err(invalid(
+                "product_id",
+                format!("Product {} is not on purchase order {}", requested.product_id, order.order_number),
+            ));
+        }
+        let receivable: Vec<usize> = candidates.iter().copied().filter(|&i| !order.lines[i].status.is_closed()).collect();
+        if receivable.is_empty() {
+            let status = order.lines[candidates[0]].status;
+            let state = match status {
+                LineStatus::Cancelled => "cancelled",
+                LineStatus::ClosedShort => "closed short",
+                _ => "fully received",
+            };
+            return Err(invalid(
+                "product_id",
+                format!("Product {} is {} on purchase order {}", requested.product_id, state, order.order_number),
+            ));
+        }
+
+        let first = &order.lines[receivable[0]];
+        if requested.quantity > 0 && first.batch_required && given(&requested.batch_number).is_none() {
+            return Err(invalid(
+                "batch_number",
+                format!("Product {} needs a batch number", requested.product_id),
+            ));
+        }
+        if first.serialized {
+            if requested.serial_numbers.len() != requested.quantity as usize {
+                return Err(invalid(
+                    "serial_numbers",
+                    format!(
+                        "{} units of product {} need as many serial numbers, got {}",
+                        requested.quantity,
+                        requested.product_id,
+                        requested.serial_numbers.len()
+                    ),
+                ));
+            }
+            if let Some(duplicate) = requested.serial_numbers.iter().find(|serial| !serials.insert(serial.as_str())) {
+                return Err(invalid("serial_numbers", format!("Serial {} is received twice", duplicate)));
+            }
+        } else if !requested.serial_numbers.is_empty() {
+            return Err(invalid(
+                "serial_numbers",
+                format!("Product {} is not serialized", requested.product_id),
+            ));
+        }
+
+        // The ordered quantities first, then the tolerance on top of them
+        let mut booked: Vec<(usize, i32)> = Vec::new();
+        let mut remaining = requested.quantity;
+        for tolerance in [false, true] {
+            for &index in &receivable {
+                let line = &order.lines[index];
+                let limit = line.quantity_ordered + if tolerance { settings.allowance(line.quantity_ordered) } else { 0 };
+                let quantity = remaining.min((limit - received[index]).max(0));
+                if quantity == 0 {
+                    continue;
+                }
+                received[index] += quantity;
+                remaining -= quantity;
+                match booked.iter_mut().find(|(booked_index, _)| *booked_index == index) {
+                    Some((_, booked_quantity)) => *booked_quantity += quantity,
+                    None => booked.push((index, quantity)),
+                }
+            }
+        }
+        if remaining > 0 {
+            return Err(invalid(
+                "quantity",
+                format!(
+                    "{} more units of product {} than are open on purchase order {}, tolerance included",
+                    remaining, requested.product_id, order.order_number
+                ),
+            ));
+        }
+        if booked.is_empty() {
+            // Closing short without a further delivery
+            booked.push((receivable[0], 0));
+        }
+
+        let mut serial_numbers = requested.serial_numbers.iter().cloned();
+        for (index, quantity) in booked {
+            let line = &order.lines[index];
+            receipt.push(ReceiptLine {
+                line_id: line.line_id,
+                product_id: line.product_id,
+                quantity,
+                bin_code: requested.bin_code.clone(),
+                batch_number: requested.batch_number.clone(),
+                serial_numbers: serial_numbers.by_ref().take(quantity as usize).collect(),
+                close_short_reason: closing.map(str::to_string),
+            });
+        }
+    }
+    Ok(receipt)
+}
+
+/// Quantities and statuses of the lines the receipt touches, once booked
+pub fn line_updates(order: &ReceivableOrder, receipt: &[ReceiptLine]) -> Vec<LineUpdate> {
+    order
+        .lines
+        .iter()
+        .filter_map(|line| {
+            let booked: Vec<&ReceiptLine> = receipt.iter().filter(|received| received.line_id == line.line_id).collect();
+            if booked.is_empty() {
+                return None;
+            }
+            let quantity_received = line.quantity_received + booked.iter().map(|received| received.quantity).sum::<i32>();
+            let close_short_reason = booked.iter().find_map(|received| received.close_short_reason.clone());
+            let status = if quantity_received >= line.quantity_ordered {
+                LineStatus::Received
+            } else if close_short_reason.is_some() {
+                LineStatus::ClosedShort
+            } else if quantity_received > 0 {
+                LineStatus::PartiallyReceived
+            } else {
+                line.status
+            };
+            Some(LineUpdate {
+                line_id: line.line_id,
+                quantity_received_before: line.quantity_received,
+                status_before: line.status,
+                quantity_received,
+                status,
+                close_short_reason: close_short_reason.filter(|_| status == LineStatus::ClosedShort),
+            })
+        })
+        .collect()
+}
+
+/// Status of the order once the receipt is booked
+pub fn status_after(order: &ReceivableOrder, receipt: &[ReceiptLine]) -> &'static str {
+    let updates = line_updates(order, receipt);
+    let statuses: Vec<LineStatus> = order
+        .lines
+        .iter()
+        .map(|line| {
+            updates
+                .iter()
+                .find(|update| update.line_id == line.line_id)
+                .map_or(line.status, |update| update.status)
+        })
+        .collect();
+    if !statuses.iter().all(|status| status.is_closed()) {
+        "partially_received"
+    } else if statuses.contains(&LineStatus::ClosedShort) {
+        "closed_short"
+    } else {
+        "received"
+    }
+}
+
+/// Batch a receipt line's units are booked into
+pub fn batch_number(order: &ReceivableOrder, line: &ReceiptLine) -> String {
+    given(&line.batch_number).unwrap_or(&order.order_number).to_string()
+}
+
+/// What the receipt brings to the order's location, for the capacity check
+pub fn incoming(receipt: &[ReceiptLine]) -> Vec<IncomingQuantity> {
+    receipt
+        .iter()
+        .filter(|line| line.quantity > 0)
+        .map(|line| IncomingQuantity {
+            product_id: line.product_id,
+            bin_code: line.bin_code.clone(),
+            quantity: line.quantity as i64,
+        })
+        .collect()
+}
+
+/// Stock movements booking the receipt into the order's location, one per
+/// receipt line with units
+pub fn movements(order: &ReceivableOrder, receipt: &[ReceiptLine], operator: Uuid, now: DateTime<Utc>) -> Vec<InventoryMovement> {
+    receipt
+        .iter()
+        .filter(|line| line.quantity > 0)
+        .map(|line| InventoryMovement {
+            id: Some(Uuid::new_v4()),
+            product_id: Some(line.product_id),
+            location_id: Some(order.location_id),
+            movement_type: Some("receipt".to_string()),
+            quantity: Some(line.quantity),
+            unit_cost: None,
+            reference_document: Some("purchase_order".to_string()),
+            reference_number: Some(order.order_number.clone()),
+            reason: None,
+            batch_number: Some(batch_number(order, line)),
+            serial_numbers: (!line.serial_numbers.is_empty()).then(|| line.serial_numbers.clone()),
+            expiry_date: None,
+            operator_id: Some(operator),
+            operator_name: None,
+            created_at: Some(now),
+            effective_date: Some(now),
+            audit_trail: None,
+        })
+        .collect()
+}
+
+/// Everything booking the planned receipt writes
+pub fn book(
+    order: &ReceivableOrder,
+    lines: Vec<ReceiptLine>,
+    tenant_id: Uuid,
+    operator: Uuid,
+    now: DateTime<Utc>,
+) -> ReceiptBooking {
+    let receipt_id = Uuid::new_v4();
+    let updates = line_updates(order, &lines);
+    let movements = movements(order, &lines, operator, now);
+    let line_status = |line_id: Uuid| {
+        updates
+            .iter()
+            .find(|update| update.line_id == line_id)
+            .map_or(LineStatus::Open, |update| update.status)
+    };
+
+    let mut serial_units = Vec::new();
+    let mut serial_events = Vec::new();
+    let mut events = Vec::new();
+    for (line, movement) in lines.iter().filter(|line| line.quantity > 0).zip(&movements) {
+        let movement_id = movement.id.unwrap_or_default();
+        let context = SerialEventContext {
+            tenant_id,
+            movement_id: Some(movement_id),
+            reference: Some(order.order_number.clone()),
+            performed_by: operator,
+            occurred_at: now,
+        };
+        for serial in &line.serial_numbers {
+            let (unit, event) =
+                SerialUnit::receive(line.product_id, serial.clone(), order.location_id, line.bin_code.clone(), movement_id, &context);
+            serial_units.push(unit);
+            serial_events.push(event);
+        }
+        events.push(ReceiptEvent {
+            id: Uuid::new_v4(),
+            receipt_id,
+            purchase_order_id: order.id,
+            line_id: line.line_id,
+            product_id: line.product_id,
+            kind: ReceiptEventKind::Received,
+            quantity: line.quantity,
+            bin_code: line.bin_code.clone(),
+            batch_number: Some(batch_number(order, line)),
+            serial_numbers: line.serial_numbers.clone(),
+            movement_id: Some(movement_id),
+            reason: None,
+            line_status: line_status(line.line_id),
+            received_by: operator,
+            received_at: now,
+        });
+    }
+    for update in updates.iter().filter(|update| update.status == LineStatus::ClosedShort) {
+        let line = order.lines.iter().find(|line| line.line_id == update.line_id);
+        events.push(ReceiptEvent {
+            id: Uuid::new_v4(),
+            receipt_id,
+            purchase_order_id: order.id,
+            line_id: update.line_id,
+            product_id: line.map_or_else(Uuid::nil, |line| line.product_id),
+            kind: ReceiptEventKind::ClosedShort,
+            quantity: line.map_or(0, |line| line.quantity_ordered) - update.quantity_received,
+            bin_code: None,
+            batch_number: None,
+            serial_numbers: Vec::new(),
+            movement_id: None,
+            reason: update.close_short_reason.clone(),
+            line_status: LineStatus::ClosedShort,
+            received_by: operator,
+            received_at: now,
+        });
+    }
+
+    ReceiptBooking {
+        receipt_id,
+        status: status_after(order, &lines),
+        lines,
+        updates,
+        movements,
+        serial_units,
+        serial_events,
+        events,
+    }
+}
+
+/// Group an order's receipt events by line
+pub fn history(order: &ReceivableOrder, events: Vec<ReceiptEvent>) -> ReceiptHistory {
+    let lines = order
+        .lines
+        .iter()
+        .map(|line| LineReceiptHistory {
+            line_id: line.line_id,
+            product_id: line.product_id,
+            quantity_ordered: line.quantity_ordered,
+            quantity_received: line.quantity_received,
+            status: line.status,
+            events: events.iter().filter(|event| event.line_id == line.line_id).cloned().collect(),
+        })
+        .collect();
+    ReceiptHistory {
+        purchase_order_id: order.id,
+        order_number: order.order_number.clone(),
+        status: order.status.clone(),
+        lines,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inventory::capacity::{self, CapacitySettings, CapacityUnit, LocationCapacity, StoredQuantity};
+    use std::collections::HashMap;
+
+    fn order(lines: &[(Uuid, i32, i32)]) -> ReceivableOrder {
+        ReceivableOrder {
+            id: Uuid::new_v4(),
+            order_number: "PO-1001".to_string(),
+            location_id: Uuid::new_v4(),
+            status: "ordered".to_string(),
+            lines: lines
+                .iter()
+                .map(|&(product_id, quantity_ordered, quantity_received)| ReceivableLine {
+                    line_id: Uuid::new_v4(),
+                    product_id,
+                    quantity_ordered,
+                    quantity_received,
+                    status: if quantity_received >= quantity_ordered {
+                        LineStatus::Received
+                    } else if quantity_received > 0 {
+                        LineStatus::PartiallyReceived
+                    } else {
+                        LineStatus::Open
+                    },
+                    batch_required: false,
+                    serialized: false,
+                })
+                .collect(),
+        }
+    }
+
+    fn line(product_id: Uuid, quantity: i32) -> ReceiptLineRequest {
+        ReceiptLineRequest {
+            product_id,
+            quantity,
+            line_id: None,
+            bin_code: None,
+            batch_number: None,
+            serial_numbers: Vec::new(),
+            close_short_reason: None,
+        }
+    }
+
+    fn request(lines: &[(Uuid, i32)]) -> ReceivePurchaseOrderRequest {
+        ReceivePurchaseOrderRequest {
+            lines: lines.iter().map(|&(product_id, quantity)| line(product_id, quantity)).collect(),
+            override_capacity: false,
+        }
+    }
+
+    fn plan(order: &ReceivableOrder, request: &ReceivePurchaseOrderRequest) -> Result<Vec<ReceiptLine>> {
+        plan_receipt(order, request, &ReceivingSettings::default())
+    }
+
+    /// The order as the repository leaves it after the booking
+    fn booked(order: &ReceivableOrder, booking: &ReceiptBooking) -> ReceivableOrder {
+        let mut order = order.clone();
+        order.status = booking.status.to_string();
+        for update in &booking.updates {
+            let line = order.lines.iter_mut().find(|line| line.line_id == update.line_id).unwrap();
+            line.quantity_received = update.quantity_received;
+            line.status = update.status;
+        }
+        order
+    }
+
+    #[test]
+    fn test_receipt_fills_open_lines_in_order() {
+        let product = Uuid::new_v4();
+        let order = order(&[(product, 10, 8), (product, 5, 0)]);
+
+        let receipt = plan(&order, &request(&[(product, 4)])).unwrap();
+
+        let booked: Vec<(Uuid, i32)> = receipt.iter().map(|line| (line.line_id, line.quantity)).collect();
+        assert_eq!(booked, vec![(order.lines[0].line_id, 2), (order.lines[1].line_id, 2)]);
+        assert_eq!(status_after(&order, &receipt), "partially_received");
+        let rest = plan(&order, &request(&[(product, 7)])).unwrap();
+        assert_eq!(status_after(&order, &rest), "received");
+    }
+
+    #[test]
+    fn test_receipt_beyond_the_order_is_refused() {
+        let product = Uuid::new_v4();
+        let order = order(&[(product, 10, 8)]);
+
+        assert!(plan(&order, &request(&[(product, 3)])).is_err());
+        assert!(plan(&order, &request(&[(Uuid::new_v4(), 1)])).is_err());
+        assert!(plan(&order, &request(&[(product, 0)])).is_err());
+
+        let mut closed = order.clone();
+        closed.status = "received".to_string();
+        assert!(plan(&closed, &request(&[(product, 1)])).is_err());
+    }
+
+    #[test]
+    fn test_units_are_booked_into_the_given_batch_or_the_order() {
+        let product = Uuid::new_v4();
+        let order = order(&[(product, 10, 0)]);
+        let mut request = request(&[(product, 4), (product, 2)]);
+        request.lines[0].batch_number = Some("LOT-7781".to_string());
+
+        let receipt = plan(&order, &request).unwrap();
+        let batches: Vec<String> = receipt.iter().map(|line| batch_number(&order, line)).collect();
+        assert_eq!(batches, vec!["LOT-7781".to_string(), order.order_number.clone()]);
+    }
+
+    #[test]
+    fn test_receipt_that_would_exceed_capacity_needs_an_override() {
+        let product = Uuid::new_v4();
+        let order = order(&[(product, 50, 0)]);
+        let capacities = [LocationCapacity {
+            location_id: order.location_id,
+            bin_code: None,
+            unit: CapacityUnit::Units,
+            capacity: 100.0,
+        }];
+        let stored = [StoredQuantity { product_id: product, bin_code: None, quantity: 70 }];
+
+        let receipt = plan(&order, &request(&[(product, 40)])).unwrap();
+        let assessment = capacity::assess(
+            order.location_id,
+            &capacities,
+            &stored,
+            &incoming(&receipt),
+            &HashMap::new(),
+            &CapacitySettings::default(),
+        );
+
+        assert!(matches!(
+            capacity::enforce(&assessment, false, true),
+            Err(MasterDataError::LocationCapacityExceeded { .. })
+        ));
+        assert!(capacity::enforce(&assessment, true, true).is_ok());
+
+        let movements = movements(&order, &receipt, Uuid::new_v4(), Utc::now());
+        assert_eq!(movements[0].location_id, Some(order.location_id));
+        assert_eq!(movements[0].quantity, Some(40));
+    }
+
+    #[test]
+    fn test_partial_receipts_across_two_events() {
+        let product = Uuid::new_v4();
+        let operator = Uuid::new_v4();
+        let order = order(&[(product, 10, 0)]);
+
+        let first = book(&order, plan(&order, &request(&[(product, 4)])).unwrap(), Uuid::new_v4(), operator, Utc::now());
+        assert_eq!(first.status, "partially_received");
+        assert_eq!(first.updates[0].status, LineStatus::PartiallyReceived);
+        let order = booked(&order, &first);
+
+        let second = book(&order, plan(&order, &request(&[(product, 6)])).unwrap(), Uuid::new_v4(), operator, Utc::now());
+        assert_eq!(second.status, "received");
+        assert_eq!(second.updates[0].quantity_received_before, 4);
+        assert_eq!(second.updates[0].quantity_received, 10);
+        let order = booked(&order, &second);
+        assert!(plan(&order, &request(&[(product, 1)])).is_err());
+
+        let events: Vec<ReceiptEvent> = first.events.iter().chain(&second.events).cloned().collect();
+        let history = history(&order, events);
+        let line = &history.lines[0];
+        assert_eq!(line.status, LineStatus::Received);
+        let received: Vec<(i32, LineStatus)> = line.events.iter().map(|event| (event.quantity, event.line_status)).collect();
+        assert_eq!(received, vec![(4, LineStatus::PartiallyReceived), (6, LineStatus::Received)]);
+        assert!(line.events.iter().all(|event| event.received_by == operator && event.movement_id.is_some()));
+        assert_ne!(line.events[0].receipt_id, line.events[1].receipt_id);
+    }
+
+    #[test]
+    fn test_over_receipt_within_tolerance_only() {
+        let product = Uuid::new_v4();
+        let order = order(&[(product, 20, 0), (product, 10, 0)]);
+        let settings = ReceivingSettings { over_receipt_tolerance_percent: 10.0 };
+
+        let receipt = plan_receipt(&order, &request(&[(product, 33)]), &settings).unwrap();
+        let booked: Vec<i32> = receipt.iter().map(|line| line.quantity).collect();
+        assert_eq!(booked, vec![22, 11]);
+        assert_eq!(status_after(&order, &receipt), "received");
+
+        assert!(plan_receipt(&order, &request(&[(product, 34)]), &settings).is_err());
+        assert!(plan(&order, &request(&[(product, 31)])).is_err());
+    }
+
+    #[test]
+    fn test_line_closed_short_takes_no_further_receipts() {
+        let product = Uuid::new_v4();
+        let other = Uuid::new_v4();
+        let order = order(&[(product, 10, 0), (other, 5, 0)]);
+        let mut closing = line(product, 6);
+        closing.line_id = Some(order.lines[0].line_id);
+        closing.close_short_reason = Some("Supplier discontinued the item".to_string());
+        let request = ReceivePurchaseOrderRequest {
+            lines: vec![closing.clone(), line(other, 5)],
+            override_capacity: false,
+        };
+
+        let booking = book(&order, plan(&order, &request).unwrap(), Uuid::new_v4(), Uuid::new_v4(), Utc::now());
+        assert_eq!(booking.status, "closed_short");
+        assert_eq!(booking.updates[0].status, LineStatus::ClosedShort);
+        assert_eq!(booking.updates[0].close_short_reason.as_deref(), Some("Supplier discontinued the item"));
+        let short = booking.events.iter().find(|event| event.kind == ReceiptEventKind::ClosedShort).unwrap();
+        assert_eq!((short.line_id, short.quantity), (order.lines[0].line_id, 4));
+        assert_eq!(booking.movements.len(), 2);
+
+        let order = booked(&order, &booking);
+        assert!(plan(&order, &self::request(&[(product, 1)])).is_err());
+
+        // Without a delivery, but only for a named line and with a reason
+        let open = self::order(&[(product, 10, 3)]);
+        let mut only_close = line(product, 0);
+        only_close.close_short_reason = Some("Not needed anymore".to_string());
+        let mut request = self::request(&[]);
+        request.lines = vec![only_close.clone()];
+        assert!(plan(&open, &request).is_err());
+        request.lines[0].line_id = Some(open.lines[0].line_id);
+        let booking = book(&open, plan(&open, &request).unwrap(), Uuid::new_v4(), Uuid::new_v4(), Utc::now());
+        assert!(booking.movements.is_empty());
+        assert_eq!(booking.status, "closed_short");
+        request.lines[0].close_short_reason = Some("  ".to_string());
+        assert!(plan(&open, &request).is_err());
+    }
+
+    #[test]
+    fn test_cancelled_lines_are_not_received() {
+        let product = Uuid::new_v4();
+        let mut order = order(&[(product, 10, 0), (product, 5, 0)]);
+        order.lines[0].status = LineStatus::Cancelled;
+
+        let receipt = plan(&order, &request(&[(product, 5)])).unwrap();
+        assert_eq!(receipt[0].line_id, order.lines[1].line_id);
+        assert_eq!(status_after(&order, &receipt), "received");
+        assert!(plan(&order, &request(&[(product, 6)])).is_err());
+
+        let mut cancelled = line(product, 1);
+        cancelled.line_id = Some(order.lines[0].line_id);
+        let mut request = request(&[]);
+        request.lines = vec![cancelled];
+        assert!(plan(&order, &request).is_err());
+    }
+
+    #[test]
+    fn test_receipt_with_an_invalid_batch_line_books_nothing() {
+        let product = Uuid::new_v4();
+        let tracked = Uuid::new_v4();
+        let mut order = order(&[(product, 10, 0), (tracked, 10, 0)]);
+        order.lines[1].batch_required = true;
+        let mut request = request(&[(product, 10), (tracked, 4)]);
+
+        match plan(&order, &request) {
+            Err(MasterDataError::ValidationError { field, .. }) => assert_eq!(field, "batch_number"),
+            other => panic!("expected a batch validation error, got {:?}", other),
+        }
+
+        request.lines[1].batch_number = Some("LOT-1".to_string());
+        let booking = book(&order, plan(&order, &request).unwrap(), Uuid::new_v4(), Uuid::new_v4(), Utc::now());
+        assert_eq!(booking.updates.len(), 2);
+        assert_eq!(booking.status, "partially_received");
+    }
+
+    #[test]
+    fn test_serialized_units_need_one_serial_each() {
+        let product = Uuid::new_v4();
+        let mut order = order(&[(product, 2, 0), (product, 2, 0)]);
+        for line in &mut order.lines {
+            line.serialized = true;
+        }
+        let mut request = request(&[(product, 3)]);
+        assert!(plan(&order, &request).is_err());
+
+        request.lines[0].serial_numbers = vec!["SN-1".to_string(), "SN-2".to_string(), "SN-1".to_string()];
+        assert!(plan(&order, &request).is_err());
+
+        request.lines[0].serial_numbers[2] = "SN-3".to_string();
+        let booking = book(&order, plan(&order, &request).unwrap(), Uuid::new_v4(), Uuid::new_v4(), Utc::now());
+        let serials: Vec<Vec<String>> = booking.lines.iter().map(|line| line.serial_numbers.clone()).collect();
+        assert_eq!(serials, vec![vec!["SN-1".to_string(), "SN-2".to_string()], vec!["SN-3".to_string()]]);
+        assert_eq!(booking.serial_units.len(), 3);
+        assert_eq!(booking.serial_units[2].receipt_movement_id, booking.movements[1].id.unwrap());
+    }
+}
@@ -4,16 +4,53 @@
 //! for multi-location scenarios and advanced analytics.
 
 use crate::inventory::model::*;
+use crate::inventory::serial::{SerialEvent, SerialStatus, SerialUnit};
+use crate::inventory::optimization::InventoryOptimizationReport;
+use crate::inventory::returns::{Disposition, ReturnOrder, ReturnOrderLine, ReturnReason, ReturnStatus};
+use crate::inventory::scenario::{ForecastScenario, IncomingSupply, SupplySource};
+use crate::inventory::stocktake::{
+    CountedProduct, FileLineChanges, ScanLine, StockPosition, StocktakeSession, StocktakeStatus,
+};
+use crate::inventory::reservation_drift::{
+    plan_repair, ReconciliationScope, RepairOutcome, ReservationAdjustment, ReservationDrift, HOLDING_TRANSFER_STATUSES,
+    SWEEP_SLICES,
+};
+use crate::inventory::picking::{
+    PickCandidate, PickLine, PickLineStatus, PickWave, Reallocation, ShortPickResolution, StockOption, WaveClosing,
+    WaveSelection, WaveStatus,
+};
+use crate::inventory::transfer_approval::{
+    ApprovalDecision, ReservationChange, TransferApproval, TransferApprovalRule, TransferFacts, TransferListFilter,
+    TransferRecord,
+};
+use crate::inventory::capacity::{
+    CapacityUnit, LocationCapacity, ProductSpace, StoredQuantity, INBOUND_TRANSFER_STATUSES,
+};
+use crate::inventory::receiving::{
+    self, LineStatus, ReceiptBooking, ReceiptEvent, ReceiptEventKind, ReceivableLine, ReceivableOrder,
+};
+use crate::inventory::valuation::StockValueLine;
+use crate::inventory::consignment::{ConsignmentBalance, ConsignmentMovement, ConsignmentMovementKind};
+use crate::inventory::atp::{AtpStock, ScheduledReceipt};
+use crate::inventory::snapshot::{
+    abc_label, parse_abc_label, SnapshotLine, SnapshotRun, SnapshotSchedule, SnapshotScope, SnapshotTrigger,
+};
+use crate::inventory::costing::{
+    self, CostCandidates, CostRestatement, CostSource, CostedMovement, CostingSettings, ResolvedCost,
+};
+use crate::supplier::catalog::{select_best_entry, CatalogQuoteRequest, PriceBreak, SupplierCatalogEntry};
 // use crate::product::model::AlertStatus; // Using inventory::model::AlertStatus instead
 use crate::types::ValuationMethod;
 use crate::utils::*;
-use crate::error::Result;
+use crate::error::{MasterDataError, Result};
 use async_trait::async_trait;
-use chrono::{DateTime, Utc};
+use erp_core::actor::ensure_actor;
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
-use sqlx::{Pool, Postgres, Row, FromRow};
+use sqlx::{PgConnection, Pool, Postgres, Row, FromRow};
 use uuid::Uuid;
 use std::collections::HashMap;
+use std::sync::Arc;
 
 #[async_trait]
 pub trait InventoryRepository: Send + Sync {
@@ -22,6 +59,8 @@ pub trait InventoryRepository: Send + Sync {
     async fn get_all_location_inventories(&self, product_id: Uuid) -> Result<Vec<LocationInventory>>;
     async fn update_inventory_levels(&self, location_id: Uuid, product_id: Uuid, request: UpdateInventoryRequest) -> Result<LocationInventory>;
     async fn get_inventory_by_location(&self, location_id: Uuid) -> Result<Vec<LocationInventory>>;
+    /// Stock figures for many product/location pairs in one round trip; unknown pairs are omitted
+    async fn get_stock_availability(&self, pairs: &[(Uuid, Uuid)]) -> Result<Vec<StockAvailability>>;
     async fn get_inventory_summary(&self, criteria: InventorySearchCriteria) -> Result<Vec<LocationInventory>>;
 
     // Movement Tracking
@@ -30,10 +69,14 @@ pub trait InventoryRepository: Send + Sync {
     async fn get_movements_by_date_range(&self, location_id: Uuid, start_date: DateTime<Utc>, end_date: DateTime<Utc>) -> Result<Vec<InventoryMovement>>;
 
     // Stock Transfers
+    /// Store the transfer and ship it: the quantity leaves the source's available
+    /// stock for its in-transit stock in the same transaction
     async fn create_stock_transfer(&self, transfer: StockTransfer) -> Result<StockTransfer>;
     async fn update_stock_transfer(&self, transfer_id: Uuid, status: TransferStatus, notes: Option<String>) -> Result<StockTransfer>;
     async fn get_stock_transfer(&self, transfer_id: Uuid) -> Result<StockTransfer>;
     async fn get_pending_transfers(&self, location_id: Option<Uuid>) -> Result<Vec<StockTransfer>>;
+    /// Move `quantity_received` from in transit into the destination's available
+    /// stock; partial receipts leave the transfer `partially_received`
     async fn process_transfer_receipt(&self, transfer_id: Uuid, quantity_received: i32, received_by: Uuid) -> Result<StockTransfer>;
 
     // Reservations
@@ -114,16 +157,116 @@ pub enum TurnoverClassification {
     Dead,         // No turnover
 }
 
+/// Told about every inventory alert once it is stored, e.g. to push it to
+/// the clients watching the tenant's alerts
+#[async_trait]
+pub trait InventoryAlertPublisher: Send + Sync {
+    async fn publish(&self, alert: &InventoryAlert) -> Result<()>;
+}
+
 pub struct PostgresInventoryRepository {
     pool: Pool<Postgres>,
+    /// Location types whose stock is never reported as sellable
+    quarantine_location_types: Vec<String>,
+    alert_publisher: Option<Arc<dyn InventoryAlertPublisher>>,
 }
 
 impl PostgresInventoryRepository {
     pub fn new(pool: Pool<Postgres>) -> Self {
-        Self { pool }
+        Self {
+            pool,
+            quarantine_location_types: vec!["quarantine".to_string()],
+            alert_publisher: None,
+        }
+    }
+
+    pub fn with_quarantine_location_types(mut self, location_types: Vec<String>) -> Self {
+        self.quarantine_location_types = location_types;
+        self
+    }
+
+    /// Publish created alerts; a failed publish is logged, the alert stays created
+    pub fn with_alert_publisher(mut self, publisher: Arc<dyn InventoryAlertPublisher>) -> Self {
+        self.alert_publisher = Some(publisher);
+        self
+    }
+
+    /// Record one leg of a stock transfer in `inventory_transactions`
+    async fn insert_transfer_movement(
+        tx: &mut sqlx::Transaction<'_, Postgres>,
+        transfer: &StockTransfer,
+        leg: TransferMovement,
+    ) -> Result<()> {
+        let movement = InventoryMovement {
+            id: Some(Uuid::new_v4()),
+            product_id: Some(transfer.product_id),
+            location_id: Some(leg.location_id),
+            movement_type: Some(leg.movement_type.to_string()),
+            quantity: Some(leg.quantity),
+            unit_cost: None,
+            reference_document: None,
+            reference_number: None,
+            reason: Some(leg.reason.to_string()),
+            batch_number: None,
+            serial_numbers: None,
+            expiry_date: None,
+            operator_id: Some(leg.operator_id),
+            operator_name: None,
+            created_at: Some(leg.at),
+            effective_date: Some(leg.at),
+            audit_trail: None,
+        };
+        let cost = resolve_movement_cost(tx, &movement).await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO inventory_transactions (
+                id, transaction_number, transaction_type, product_id, location_id, quantity_change,
+                unit_cost, cost_source, cost_needs_review, reference_type, reference_id, reason_code, status,
+                created_by, created_at, transaction_date
+            )
+            VALUES ($1, CONCAT('TXN-', EXTRACT(EPOCH FROM NOW())), $2::movement_type, $3, $4, $5, $6, $7, $8, $9, $10,
+                    $11, $12, $13, $14, $14)
+            "#,
+        )
+        .bind(movement.id)
+        .bind(leg.movement_type)
+        .bind(transfer.product_id)
+        .bind(leg.location_id)
+        .bind(leg.quantity)
+        .bind(cost.map(|c| c.unit_cost))
+        .bind(cost.map(|c| c.source.as_str()))
+        .bind(cost.is_some_and(|c| c.needs_review))
+        .bind(TRANSFER_REFERENCE_TYPE)
+        .bind(transfer.id)
+        .bind(leg.reason)
+        .bind(leg.status)
+        .bind(leg.operator_id)
+        .bind(leg.at)
+        .execute(&mut **tx)
+        .await?;
+        Ok(())
     }
 }
 
+/// `reference_type` of movements booked for a stock transfer
+const TRANSFER_REFERENCE_TYPE: &str = "stock_transfer";
+const TRANSFER_OUT_REASON: &str = "transfer_out";
+/// Pending until the whole transfer has been received
+const TRANSFER_IN_TRANSIT_REASON: &str = "transfer_in_transit";
+const TRANSFER_RECEIPT_REASON: &str = "transfer_receipt";
+
+/// One movement booked for a stock transfer
+struct TransferMovement {
+    location_id: Uuid,
+    movement_type: &'static str,
+    quantity: i32,
+    reason: &'static str,
+    status: &'static str,
+    operator_id: Uuid,
+    at: DateTime<Utc>,
+}
+
 #[async_trait]
 impl InventoryRepository for PostgresInventoryRepository {
     async fn get_location_inventory(&self, product_id: Uuid, location_id: Uuid) -> Result<LocationInventory> {
@@ -271,14 +414,36 @@ impl InventoryRepository for PostgresInventoryRepository {
     async fn update_inventory_levels(&self, location_id: Uuid, product_id: Uuid, request: UpdateInventoryRequest) -> Result<LocationInventory> {
         let mut tx = self.pool.begin().await?;
 
+        let unit_cost = request.unit_cost.map(|v| rust_decimal::Decimal::from_f64_retain(v).unwrap_or_default());
+        let inbound = InventoryMovement {
+            id: None,
+            product_id: Some(product_id),
+            location_id: Some(location_id),
+            movement_type: None,
+            quantity: Some(request.quantity_change),
+            unit_cost,
+            reference_document: request.reference_document.clone(),
+            reference_number: None,
+            reason: None,
+            batch_number: None,
+            serial_numbers: None,
+            expiry_date: None,
+            operator_id: None,
+            operator_name: None,
+            created_at: None,
+            effective_date: request.effective_date,
+            audit_trail: None,
+        };
+        let cost = resolve_movement_cost(&mut tx, &inbound).await?;
+
         // Create inventory movement record
         let row = sqlx::query!(
             r#"
             INSERT INTO inventory_transactions (
                 id, transaction_number, transaction_type, transaction_date, product_id, location_id,
-                quantity_change, unit_cost, reference_document, reason_code
+                quantity_change, unit_cost, cost_source, cost_needs_review, reference_document, reason_code
             )
-            VALUES ($1, CONCAT('TXN-', EXTRACT(EPOCH FROM NOW())), $2, $3, $4, $5, $6, $7, $8, $9)
+            VALUES ($1, CONCAT('TXN-', EXTRACT(EPOCH FROM NOW())), $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
             RETURNING
                 id,
                 product_id,
@@ -301,7 +466,9 @@ impl InventoryRepository for PostgresInventoryRepository {
             product_id,
             location_id,
             request.quantity_change,
-            request.unit_cost.map(|v| rust_decimal::Decimal::from_f64_retain(v).unwrap_or_default()),
+            cost.map(|c| c.unit_cost).or(unit_cost),
+            cost.map(|c| c.source.as_str()),
+            cost.is_some_and(|c| c.needs_review),
             request.reference_document,
             request.reason
         )
@@ -403,6 +570,52 @@ impl InventoryRepository for PostgresInventoryRepository {
         Ok(updated_inventory)
     }
 
+    async fn get_stock_availability(&self, pairs: &[(Uuid, Uuid)]) -> Result<Vec<StockAvailability>> {
+        if pairs.is_empty() {
+            return Ok(Vec::new());
+        }
+        let (product_ids, location_ids): (Vec<Uuid>, Vec<Uuid>) = pairs.iter().copied().unzip();
+
+        let rows = sqlx::query(
+            r#"
+            SELECT li.product_id, li.location_id, li.quantity_available, li.quantity_reserved,
+                   li.quantity_on_order, li.quantity_in_transit,
+                   COALESCE((SELECT SUM(cs.quantity) FROM consignment_stock cs
+                             WHERE cs.product_id = li.product_id AND cs.location_id = li.location_id), 0)::INTEGER
+                       AS consignment_quantity,
+                   COALESCE((SELECT SUM(pb.quantity) FROM public.product_batches pb
+                             WHERE pb.product_id = li.product_id AND pb.location_id = li.location_id
+                               AND pb.quality_status IN ('failed', 'recalled') AND pb.disposition IS NULL), 0)::INTEGER
+                       AS blocked_quantity,
+                   li.location_type::text <> ALL($3::text[]) AS sellable
+            FROM location_items li
+            JOIN UNNEST($1::uuid[], $2::uuid[]) AS wanted(product_id, location_id)
+              ON li.product_id = wanted.product_id AND li.location_id = wanted.location_id
+            "#,
+        )
+        .bind(&product_ids)
+        .bind(&location_ids)
+        .bind(&self.quarantine_location_types)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                Ok(StockAvailability {
+                    product_id: row.try_get("product_id")?,
+                    location_id: row.try_get("location_id")?,
+                    quantity_available: row.try_get("quantity_available")?,
+                    quantity_reserved: row.try_get("quantity_reserved")?,
+                    quantity_on_order: row.try_get("quantity_on_order")?,
+                    quantity_in_transit: row.try_get("quantity_in_transit")?,
+                    consignment_quantity: row.try_get("consignment_quantity")?,
+                    blocked_quantity: row.try_get("blocked_quantity")?,
+                    sellable: row.try_get("sellable")?,
+                })
+            })
+            .collect()
+    }
+
     async fn get_inventory_by_location(&self, location_id: Uuid) -> Result<Vec<LocationInventory>> {
         let rows = sqlx::query!(
             r#"
@@ -573,15 +786,17 @@ impl InventoryRepository for PostgresInventoryRepository {
     }
 
     async fn create_inventory_movement(&self, movement: InventoryMovement) -> Result<InventoryMovement> {
+        let mut conn = self.pool.acquire().await?;
+        let cost = resolve_movement_cost(&mut conn, &movement).await?;
         let row = sqlx::query!(
             r#"
             INSERT INTO inventory_transactions (
                 id, transaction_number, transaction_type, product_id, location_id, quantity_change,
-                unit_cost, reference_document, reference_number, reason_code,
+                unit_cost, cost_source, cost_needs_review, reference_document, reference_number, reason_code,
                 batch_number, lot_number, expiry_date, created_by,
                 notes, created_at, transaction_date
             )
-            VALUES ($1, CONCAT('TXN-', EXTRACT(EPOCH FROM NOW())), $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16)
+            VALUES ($1, CONCAT('TXN-', EXTRACT(EPOCH FROM NOW())), $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18)
             RETURNING
                 id,
                 product_id,
@@ -603,7 +818,9 @@ impl InventoryRepository for PostgresInventoryRepository {
             movement.product_id,
             movement.location_id,
             movement.quantity,
-            movement.unit_cost,
+            cost.map(|c| c.unit_cost).or(movement.unit_cost),
+            cost.map(|c| c.source.as_str()),
+            cost.is_some_and(|c| c.needs_review),
             movement.reference_document,
             movement.reference_number,
             movement.reason,
@@ -615,7 +832,7 @@ impl InventoryRepository for PostgresInventoryRepository {
             movement.created_at,
             movement.effective_date
         )
-        .fetch_one(&self.pool)
+        .fetch_one(&mut *conn)
         .await?;
 
         let created_movement = InventoryMovement {
@@ -787,9 +1004,131 @@ impl InventoryRepository for PostgresInventoryRepository {
         Ok(movements)
     }
 
-    // Placeholder implementations for remaining methods
-    async fn create_stock_transfer(&self, transfer: StockTransfer) -> Result<StockTransfer> {
-        // Implementation would insert into stock_transfers table
+    async fn create_stock_transfer(&self, mut transfer: StockTransfer) -> Result<StockTransfer> {
+        if transfer.quantity <= 0 {
+            return Err(MasterDataError::ValidationError {
+                field: "quantity".to_string(),
+                message: "Transfer quantity must be positive".to_string(),
+            });
+        }
+        let now = Utc::now();
+        transfer.quantity_shipped = Some(transfer.quantity);
+        transfer.shipped_by = Some(transfer.requested_by);
+        transfer.shipped_date = Some(now);
+        transfer.status = TransferStatus::InTransit;
+
+        let mut tx = self.pool.begin().await?;
+
+        let shipped = sqlx::query(
+            r#"
+            UPDATE location_items
+            SET quantity_available = quantity_available - $3,
+                quantity_in_transit = quantity_in_transit + $3,
+                updated_at = NOW()
+            WHERE product_id = $1 AND location_id = $2 AND quantity_available >= $3
+            "#,
+        )
+        .bind(transfer.product_id)
+        .bind(transfer.from_location_id)
+        .bind(transfer.quantity)
+        .execute(&mut *tx)
+        .await?;
+
+        if shipped.rows_affected() == 0 {
+            let available: Option<i32> = sqlx::query_scalar(
+                "SELECT quantity_available FROM location_items WHERE product_id = $1 AND location_id = $2",
+            )
+            .bind(transfer.product_id)
+            .bind(transfer.from_location_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+            return Err(match available {
+                Some(available) => MasterDataError::ValidationError {
+                    field: "quantity".to_string(),
+                    message: format!(
+                        "Transferring {} would leave negative stock at the source location; only {} available",
+                        transfer.quantity, available
+                    ),
+                },
+                None => MasterDataError::NotFoundError(format!(
+                    "Inventory of product {} at location {}",
+                    transfer.product_id, transfer.from_location_id
+                )),
+            });
+        }
+
+        sqlx::query(
+            r#"
+            INSERT INTO stock_transfers (
+                id, product_id, from_location_id, to_location_id, quantity, quantity_shipped, quantity_received,
+                status, priority, reason, requested_by, approved_by, shipped_by, received_by, requested_date,
+                approved_date, shipped_date, received_date, actual_delivery_date, tracking_number, carrier,
+                shipping_cost, notes, created_at, created_by, expected_arrival_date
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20,
+                    $21, $22, $23, $24, $25, $26)
+            "#,
+        )
+        .bind(transfer.id)
+        .bind(transfer.product_id)
+        .bind(transfer.from_location_id)
+        .bind(transfer.to_location_id)
+        .bind(transfer.quantity)
+        .bind(transfer.quantity_shipped)
+        .bind(transfer.quantity_received)
+        .bind(&transfer.status)
+        .bind(&transfer.priority)
+        .bind(&transfer.reason)
+        .bind(transfer.requested_by)
+        .bind(transfer.approved_by)
+        .bind(transfer.shipped_by)
+        .bind(transfer.received_by)
+        .bind(transfer.requested_date)
+        .bind(transfer.approved_date)
+        .bind(transfer.shipped_date)
+        .bind(transfer.received_date)
+        .bind(transfer.actual_delivery_date)
+        .bind(&transfer.tracking_number)
+        .bind(&transfer.carrier)
+        .bind(transfer.shipping_cost)
+        .bind(&transfer.notes)
+        .bind(transfer.created_at)
+        .bind(transfer.created_by)
+        .bind(transfer.expected_arrival_date)
+        .execute(&mut *tx)
+        .await?;
+
+        // Out of the source now, into the destination once received
+        Self::insert_transfer_movement(
+            &mut tx,
+            &transfer,
+            TransferMovement {
+                location_id: transfer.from_location_id,
+                movement_type: "outbound",
+                quantity: -transfer.quantity,
+                reason: TRANSFER_OUT_REASON,
+                status: "completed",
+                operator_id: transfer.requested_by,
+                at: now,
+            },
+        )
+        .await?;
+        Self::insert_transfer_movement(
+            &mut tx,
+            &transfer,
+            TransferMovement {
+                location_id: transfer.to_location_id,
+                movement_type: "transfer",
+                quantity: transfer.quantity,
+                reason: TRANSFER_IN_TRANSIT_REASON,
+                status: "pending",
+                operator_id: transfer.requested_by,
+                at: now,
+            },
+        )
+        .await?;
+
+        tx.commit().await?;
         Ok(transfer)
     }
 
@@ -815,6 +1154,7 @@ impl InventoryRepository for PostgresInventoryRepository {
             shipped_date: if status == TransferStatus::InTransit { Some(chrono::Utc::now()) } else { None },
             received_date: if status == TransferStatus::Completed { Some(chrono::Utc::now()) } else { None },
             actual_delivery_date: None,
+            expected_arrival_date: None,
             tracking_number: None,
             carrier: None,
             shipping_cost: None,
@@ -825,34 +1165,13 @@ impl InventoryRepository for PostgresInventoryRepository {
     }
 
     async fn get_stock_transfer(&self, transfer_id: Uuid) -> Result<StockTransfer> {
-        // Return a default transfer for the given ID
-        Ok(StockTransfer {
-            id: transfer_id,
-            from_location_id: Uuid::new_v4(),
-            to_location_id: Uuid::new_v4(),
-            product_id: Uuid::new_v4(),
-            quantity: 100,
-            quantity_shipped: None,
-            quantity_received: None,
-            status: TransferStatus::Pending,
-            priority: TransferPriority::Normal,
-            reason: "Generated transfer".to_string(),
-            requested_by: Uuid::new_v4(),
-            approved_by: None,
-            shipped_by: None,
-            received_by: None,
-            requested_date: chrono::Utc::now(),
-            approved_date: None,
-            shipped_date: None,
-            received_date: None,
-            actual_delivery_date: None,
-            tracking_number: None,
-            carrier: None,
-            shipping_cost: None,
-            notes: Some("Generated transfer".to_string()),
-            created_at: chrono::Utc::now(),
-            created_by: Uuid::new_v4(),
-        })
+        let row = sqlx::query(&format!("SELECT {} FROM stock_transfers st WHERE st.id = $1", STOCK_TRANSFER_COLUMNS))
+            .bind(transfer_id)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or_else(|| MasterDataError::NotFoundError(format!("Stock transfer {}", transfer_id)))?;
+
+        StockTransfer::from_row(&row).map_err(Into::into)
     }
 
     async fn get_pending_transfers(&self, _location_id: Option<Uuid>) -> Result<Vec<StockTransfer>> {
@@ -861,34 +1180,108 @@ impl InventoryRepository for PostgresInventoryRepository {
     }
 
     async fn process_transfer_receipt(&self, transfer_id: Uuid, quantity_received: i32, received_by: Uuid) -> Result<StockTransfer> {
-        // Process transfer receipt and mark as completed
-        Ok(StockTransfer {
-            id: transfer_id,
-            from_location_id: Uuid::new_v4(),
-            to_location_id: Uuid::new_v4(),
-            product_id: Uuid::new_v4(),
-            quantity: quantity_received,
-            quantity_shipped: Some(quantity_received),
-            quantity_received: Some(quantity_received),
-            status: TransferStatus::Completed,
-            priority: TransferPriority::Normal,
-            reason: "Transfer receipt".to_string(),
-            requested_by: Uuid::new_v4(),
-            approved_by: Some(received_by),
-            shipped_by: Some(received_by),
-            received_by: Some(received_by),
-            requested_date: chrono::Utc::now(),
-            approved_date: Some(chrono::Utc::now()),
-            shipped_date: Some(chrono::Utc::now()),
-            received_date: Some(chrono::Utc::now()),
-            actual_delivery_date: Some(chrono::Utc::now()),
-            tracking_number: None,
-            carrier: None,
-            shipping_cost: None,
-            notes: Some(format!("Received by {}", received_by)),
-            created_at: chrono::Utc::now(),
-            created_by: received_by,
-        })
+        let mut tx = self.pool.begin().await?;
+
+        let row = sqlx::query(&format!(
+            "SELECT {} FROM stock_transfers st WHERE st.id = $1 FOR UPDATE",
+            STOCK_TRANSFER_COLUMNS
+        ))
+        .bind(transfer_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| MasterDataError::NotFoundError(format!("Stock transfer {}", transfer_id)))?;
+        let mut transfer = StockTransfer::from_row(&row)?;
+
+        let now = Utc::now();
+        transfer.record_receipt(quantity_received, received_by, now)?;
+
+        // The source keeps any shortfall in transit until the transfer is settled
+        let left_transit = sqlx::query(
+            r#"
+            UPDATE location_items
+            SET quantity_in_transit = quantity_in_transit - $3, updated_at = NOW()
+            WHERE product_id = $1 AND location_id = $2 AND quantity_in_transit >= $3
+            "#,
+        )
+        .bind(transfer.product_id)
+        .bind(transfer.from_location_id)
+        .bind(quantity_received)
+        .execute(&mut *tx)
+        .await?;
+        if left_transit.rows_affected() == 0 {
+            return Err(MasterDataError::ValidationError {
+                field: "quantity_received".to_string(),
+                message: format!("Source location has less than {} in transit", quantity_received),
+            });
+        }
+
+        let arrived = sqlx::query(
+            r#"
+            UPDATE location_items
+            SET quantity_available = quantity_available + $3, updated_at = NOW()
+            WHERE product_id = $1 AND location_id = $2
+            "#,
+        )
+        .bind(transfer.product_id)
+        .bind(transfer.to_location_id)
+        .bind(quantity_received)
+        .execute(&mut *tx)
+        .await?;
+        if arrived.rows_affected() == 0 {
+            return Err(MasterDataError::NotFoundError(format!(
+                "Inventory of product {} at location {}",
+                transfer.product_id, transfer.to_location_id
+            )));
+        }
+
+        sqlx::query(
+            r#"
+            UPDATE stock_transfers
+            SET status = $2, quantity_received = $3, received_by = $4, received_date = $5, actual_delivery_date = $6
+            WHERE id = $1
+            "#,
+        )
+        .bind(transfer.id)
+        .bind(&transfer.status)
+        .bind(transfer.quantity_received)
+        .bind(transfer.received_by)
+        .bind(transfer.received_date)
+        .bind(transfer.actual_delivery_date)
+        .execute(&mut *tx)
+        .await?;
+
+        Self::insert_transfer_movement(
+            &mut tx,
+            &transfer,
+            TransferMovement {
+                location_id: transfer.to_location_id,
+                movement_type: "transfer",
+                quantity: quantity_received,
+                reason: TRANSFER_RECEIPT_REASON,
+                status: "completed",
+                operator_id: received_by,
+                at: now,
+            },
+        )
+        .await?;
+
+        if transfer.status == TransferStatus::Completed {
+            sqlx::query(
+                r#"
+                UPDATE inventory_transactions
+                SET status = 'completed'
+                WHERE reference_type = $1 AND reference_id = $2 AND reason_code = $3 AND status = 'pending'
+                "#,
+            )
+            .bind(TRANSFER_REFERENCE_TYPE)
+            .bind(transfer.id)
+            .bind(TRANSFER_IN_TRANSIT_REASON)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(transfer)
     }
 
     async fn create_reservation(&self, reservation: InventoryReservation) -> Result<InventoryReservation> {
@@ -1088,6 +1481,11 @@ impl InventoryRepository for PostgresInventoryRepository {
 
     async fn create_inventory_alert(&self, alert: InventoryAlert) -> Result<InventoryAlert> {
         // Implementation would create alert
+        if let Some(publisher) = &self.alert_publisher {
+            if let Err(e) = publisher.publish(&alert).await {
+                tracing::warn!("Failed to publish inventory alert {}: {}", alert.id, e);
+            }
+        }
         Ok(alert)
     }
 
@@ -1373,4 +1771,3937 @@ impl InventoryRepository for PostgresInventoryRepository {
         // Implementation would analyze turnover
         Ok(vec![])
     }
-}
\ No newline at end of file
+}
+/// One atomic batch of serial changes.
+///
+/// Movements are recorded and applied to the location quantities in the same
+/// transaction as the unit inserts/updates, so serials never drift from stock.
+#[derive(Debug, Clone, Default)]
+pub struct SerialChangeSet {
+    pub movements: Vec<InventoryMovement>,
+    pub received: Vec<SerialUnit>,
+    /// Each updated unit must have an event whose `from_status` is the status
+    /// it is expected to be in; concurrent changes fail the whole batch
+    pub updated: Vec<SerialUnit>,
+    pub events: Vec<SerialEvent>,
+    /// Stock reserved (positive) or released (negative) with the units,
+    /// applied before the movements
+    pub reservations: Vec<ReservationChange>,
+}
+
+/// Persistence for serial units and their history
+#[async_trait]
+pub trait SerialUnitRepository: Send + Sync {
+    /// Which of the given serials already exist for the tenant
+    async fn find_existing_serials(&self, tenant_id: Uuid, serial_numbers: &[String]) -> Result<Vec<String>>;
+    async fn get_serial_unit(&self, tenant_id: Uuid, serial_number: &str) -> Result<Option<SerialUnit>>;
+    async fn list_serial_units(
+        &self,
+        tenant_id: Uuid,
+        product_id: Uuid,
+        location_id: Option<Uuid>,
+        status: Option<SerialStatus>,
+    ) -> Result<Vec<SerialUnit>>;
+    async fn get_serial_events(&self, tenant_id: Uuid, serial_unit_id: Uuid) -> Result<Vec<SerialEvent>>;
+    async fn apply_serial_changes(&self, changes: SerialChangeSet) -> Result<()>;
+}
+
+pub struct PostgresSerialUnitRepository {
+    pool: Pool<Postgres>,
+}
+
+impl PostgresSerialUnitRepository {
+    pub fn new(pool: Pool<Postgres>) -> Self {
+        Self { pool }
+    }
+
+    fn map_unit(row: &sqlx::postgres::PgRow) -> Result<SerialUnit> {
+        let status: String = row.get("status");
+        Ok(SerialUnit {
+            id: row.get("id"),
+            tenant_id: row.get("tenant_id"),
+            product_id: row.get("product_id"),
+            serial_number: row.get("serial_number"),
+            location_id: row.get("location_id"),
+            bin_location: row.get("bin_location"),
+            status: parse_serial_status(&status)?,
+            receipt_movement_id: row.get("receipt_movement_id"),
+            received_at: row.get("received_at"),
+            updated_at: row.get("updated_at"),
+        })
+    }
+
+    fn map_event(row: &sqlx::postgres::PgRow) -> Result<SerialEvent> {
+        let from_status: Option<String> = row.get("from_status");
+        let to_status: String = row.get("to_status");
+        Ok(SerialEvent {
+            id: row.get("id"),
+            tenant_id: row.get("tenant_id"),
+            serial_unit_id: row.get("serial_unit_id"),
+            serial_number: row.get("serial_number"),
+            product_id: row.get("product_id"),
+            from_status: from_status.as_deref().map(parse_serial_status).transpose()?,
+            to_status: parse_serial_status(&to_status)?,
+            from_location_id: row.get("from_location_id"),
+            to_location_id: row.get("to_location_id"),
+            movement_id: row.get("movement_id"),
+            reference: row.get("reference"),
+            performed_by: row.get("performed_by"),
+            occurred_at: row.get("occurred_at"),
+        })
+    }
+}
+
+fn parse_serial_status(value: &str) -> Result<SerialStatus> {
+    SerialStatus::parse(value).ok_or_else(|| MasterDataError::DatabaseError(format!("Unknown serial status: {}", value)))
+}
+
+const SERIAL_UNIT_COLUMNS: &str = "id, tenant_id, product_id, serial_number, location_id, bin_location, status, \
+    receipt_movement_id, received_at, updated_at";
+
+#[async_trait]
+impl SerialUnitRepository for PostgresSerialUnitRepository {
+    async fn find_existing_serials(&self, tenant_id: Uuid, serial_numbers: &[String]) -> Result<Vec<String>> {
+        let rows = sqlx::query(
+            "SELECT serial_number FROM serial_units WHERE tenant_id = $1 AND serial_number = ANY($2)",
+        )
+        .bind(tenant_id)
+        .bind(serial_numbers)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(|row| row.get("serial_number")).collect())
+    }
+
+    async fn get_serial_unit(&self, tenant_id: Uuid, serial_number: &str) -> Result<Option<SerialUnit>> {
+        let row = sqlx::query(&format!(
+            "SELECT {} FROM serial_units WHERE tenant_id = $1 AND serial_number = $2",
+            SERIAL_UNIT_COLUMNS
+        ))
+        .bind(tenant_id)
+        .bind(serial_number)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.as_ref().map(Self::map_unit).transpose()
+    }
+
+    async fn list_serial_units(
+        &self,
+        tenant_id: Uuid,
+        product_id: Uuid,
+        location_id: Option<Uuid>,
+        status: Option<SerialStatus>,
+    ) -> Result<Vec<SerialUnit>> {
+        let rows = sqlx::query(&format!(
+            r#"
+            SELECT {} FROM serial_units
+            WHERE tenant_id = $1 AND product_id = $2
+              AND ($3::uuid IS NULL OR location_id = $3)
+              AND ($4::text IS NULL OR status = $4)
+            ORDER BY received_at, serial_number
+            "#,
+            SERIAL_UNIT_COLUMNS
+        ))
+        .bind(tenant_id)
+        .bind(product_id)
+        .bind(location_id)
+        .bind(status.map(SerialStatus::as_str))
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(Self::map_unit).collect()
+    }
+
+    async fn get_serial_events(&self, tenant_id: Uuid, serial_unit_id: Uuid) -> Result<Vec<SerialEvent>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, tenant_id, serial_unit_id, serial_number, product_id, from_status, to_status,
+                   from_location_id, to_location_id, movement_id, reference, performed_by, occurred_at
+            FROM serial_unit_events
+            WHERE tenant_id = $1 AND serial_unit_id = $2
+            ORDER BY occurred_at, id
+            "#,
+        )
+        .bind(tenant_id)
+        .bind(serial_unit_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(Self::map_event).collect()
+    }
+
+    async fn apply_serial_changes(&self, changes: SerialChangeSet) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        for reservation in &changes.reservations {
+            let updated = sqlx::query(
+                r#"
+                UPDATE location_items
+                SET quantity_reserved = GREATEST(quantity_reserved + $3, 0), updated_at = NOW()
+                WHERE product_id = $1 AND location_id = $2
+                  AND ($3 < 0 OR quantity_available - quantity_reserved >= $3)
+                "#,
+            )
+            .bind(reservation.product_id)
+            .bind(reservation.location_id)
+            .bind(reservation.quantity)
+            .execute(&mut *tx)
+            .await?;
+
+            if updated.rows_affected() == 0 && reservation.quantity > 0 {
+                return Err(MasterDataError::ValidationError {
+                    field: "quantity".to_string(),
+                    message: "Insufficient unreserved inventory for the serials".to_string(),
+                });
+            }
+        }
+
+        for movement in &changes.movements {
+            let cost = resolve_movement_cost(&mut tx, movement).await?;
+            sqlx::query(
+                r#"
+                INSERT INTO inventory_transactions (
+                    id, transaction_number, transaction_type, product_id, location_id, quantity_change,
+                    unit_cost, cost_source, cost_needs_review, reference_number, reason_code, serial_numbers, created_by,
+                    created_at, transaction_date
+                )
+                VALUES ($1, CONCAT('TXN-', EXTRACT(EPOCH FROM NOW())), $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)
+                "#,
+            )
+            .bind(movement.id)
+            .bind(&movement.movement_type)
+            .bind(movement.product_id)
+            .bind(movement.location_id)
+            .bind(movement.quantity)
+            .bind(cost.map(|c| c.unit_cost).or(movement.unit_cost))
+            .bind(cost.map(|c| c.source.as_str()))
+            .bind(cost.is_some_and(|c| c.needs_review))
+            .bind(&movement.reference_number)
+            .bind(&movement.reason)
+            .bind(&movement.serial_numbers)
+            .bind(movement.operator_id)
+            .bind(movement.created_at)
+            .bind(movement.effective_date)
+            .execute(&mut *tx)
+            .await?;
+
+            let updated = sqlx::query(
+                r#"
+                UPDATE location_items
+                SET quantity_available = quantity_available + $3, updated_at = NOW()
+                WHERE product_id = $1 AND location_id = $2 AND quantity_available + $3 >= 0
+                "#,
+            )
+            .bind(movement.product_id)
+            .bind(movement.location_id)
+            .bind(movement.quantity)
+            .execute(&mut *tx)
+            .await?;
+
+            if updated.rows_affected() == 0 {
+                return Err(MasterDataError::ValidationError {
+                    field: "location_id".to_string(),
+                    message: "No inventory record with enough stock for this product at the location".to_string(),
+                });
+            }
+        }
+
+        for unit in &changes.received {
+            sqlx::query(&format!(
+                "INSERT INTO serial_units ({}) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)",
+                SERIAL_UNIT_COLUMNS
+            ))
+            .bind(unit.id)
+            .bind(unit.tenant_id)
+            .bind(unit.product_id)
+            .bind(&unit.serial_number)
+            .bind(unit.location_id)
+            .bind(&unit.bin_location)
+            .bind(unit.status.as_str())
+            .bind(unit.receipt_movement_id)
+            .bind(unit.received_at)
+            .bind(unit.updated_at)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| match &e {
+                sqlx::Error::Database(db) if db.is_unique_violation() => {
+                    MasterDataError::DuplicateSerialNumber { serial: unit.serial_number.clone() }
+                }
+                _ => MasterDataError::Database(e),
+            })?;
+        }
+
+        for unit in &changes.updated {
+            let expected = changes
+                .events
+                .iter()
+                .find(|event| event.serial_unit_id == unit.id)
+                .and_then(|event| event.from_status)
+                .ok_or_else(|| MasterDataError::Internal {
+                    message: format!("No event recorded for serial {}", unit.serial_number),
+                })?;
+
+            let updated = sqlx::query(
+                r#"
+                UPDATE serial_units
+                SET status = $3, location_id = $4, bin_location = $5, updated_at = $6
+                WHERE id = $1 AND tenant_id = $2 AND status = $7
+                "#,
+            )
+            .bind(unit.id)
+            .bind(unit.tenant_id)
+            .bind(unit.status.as_str())
+            .bind(unit.location_id)
+            .bind(&unit.bin_location)
+            .bind(unit.updated_at)
+            .bind(expected.as_str())
+            .execute(&mut *tx)
+            .await?;
+
+            if updated.rows_affected() == 0 {
+                return Err(MasterDataError::InvalidSerialTransition {
+                    serial: unit.serial_number.clone(),
+                    from: expected.as_str().to_string(),
+                    to: unit.status.as_str().to_string(),
+                });
+            }
+        }
+
+        for event in &changes.events {
+            sqlx::query(
+                r#"
+                INSERT INTO serial_unit_events (
+                    id, tenant_id, serial_unit_id, serial_number, product_id, from_status, to_status,
+                    from_location_id, to_location_id, movement_id, reference, performed_by, occurred_at
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+                "#,
+            )
+            .bind(event.id)
+            .bind(event.tenant_id)
+            .bind(event.serial_unit_id)
+            .bind(&event.serial_number)
+            .bind(event.product_id)
+            .bind(event.from_status.map(SerialStatus::as_str))
+            .bind(event.to_status.as_str())
+            .bind(event.from_location_id)
+            .bind(event.to_location_id)
+            .bind(event.movement_id)
+            .bind(&event.reference)
+            .bind(event.performed_by)
+            .bind(event.occurred_at)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+}
+
+/// Stored optimization reports, explanations included
+#[async_trait]
+pub trait OptimizationReportRepository: Send + Sync {
+    async fn save_report(&self, tenant_id: Uuid, report: &InventoryOptimizationReport) -> Result<()>;
+    async fn get_report(&self, tenant_id: Uuid, report_id: Uuid) -> Result<Option<InventoryOptimizationReport>>;
+}
+
+pub struct PostgresOptimizationReportRepository {
+    pool: Pool<Postgres>,
+}
+
+impl PostgresOptimizationReportRepository {
+    pub fn new(pool: Pool<Postgres>) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl OptimizationReportRepository for PostgresOptimizationReportRepository {
+    async fn save_report(&self, tenant_id: Uuid, report: &InventoryOptimizationReport) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO public.inventory_optimization_reports (id, tenant_id, location_id, report, created_at)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+        )
+        .bind(report.id)
+        .bind(tenant_id)
+        .bind(report.location_id)
+        .bind(serde_json::to_value(report)?)
+        .bind(report.optimization_date)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn get_report(&self, tenant_id: Uuid, report_id: Uuid) -> Result<Option<InventoryOptimizationReport>> {
+        let row = sqlx::query(
+            "SELECT report FROM public.inventory_optimization_reports WHERE tenant_id = $1 AND id = $2",
+        )
+        .bind(tenant_id)
+        .bind(report_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.map(|row| serde_json::from_value(row.get("report")).map_err(MasterDataError::from))
+            .transpose()
+    }
+}
+
+/// Persistence for stocktake sessions and their raw scan lines
+#[async_trait]
+pub trait StocktakeRepository: Send + Sync {
+    async fn create_session(&self, session: &StocktakeSession) -> Result<()>;
+    async fn get_session(&self, tenant_id: Uuid, session_id: Uuid) -> Result<Option<StocktakeSession>>;
+    /// Store the lines of one uploaded file, keeping lines whose checksum is
+    /// already stored for that file and dropping the ones no longer present
+    async fn replace_file_lines(
+        &self,
+        tenant_id: Uuid,
+        session_id: Uuid,
+        file_name: &str,
+        lines: &[ScanLine],
+    ) -> Result<FileLineChanges>;
+    async fn get_scan_lines(&self, tenant_id: Uuid, session_id: Uuid) -> Result<Vec<ScanLine>>;
+    async fn find_products_by_barcode(&self, tenant_id: Uuid, barcodes: &[String]) -> Result<HashMap<String, CountedProduct>>;
+    async fn find_locations_by_code(&self, tenant_id: Uuid, codes: &[String]) -> Result<HashMap<String, Uuid>>;
+    /// Current quantity and movements booked after `snapshot_at` for each product/location pair
+    async fn get_stock_positions(
+        &self,
+        pairs: &[(Uuid, Uuid)],
+        snapshot_at: DateTime<Utc>,
+    ) -> Result<HashMap<(Uuid, Uuid), StockPosition>>;
+    /// Persist computed results, status and cycle count links
+    async fn update_session(&self, session: &StocktakeSession) -> Result<()>;
+}
+
+pub struct PostgresStocktakeRepository {
+    pool: Pool<Postgres>,
+}
+
+impl PostgresStocktakeRepository {
+    pub fn new(pool: Pool<Postgres>) -> Self {
+        Self { pool }
+    }
+
+    fn map_session(row: &sqlx::postgres::PgRow) -> Result<StocktakeSession> {
+        let status: String = row.get("status");
+        Ok(StocktakeSession {
+            id: row.get("id"),
+            tenant_id: row.get("tenant_id"),
+            name: row.get("name"),
+            snapshot_at: row.get("snapshot_at"),
+            status: StocktakeStatus::parse(&status)
+                .ok_or_else(|| MasterDataError::DatabaseError(format!("Unknown stocktake status: {}", status)))?,
+            lines: serde_json::from_value(row.get("lines"))?,
+            unmatched_barcodes: serde_json::from_value(row.get("unmatched_barcodes"))?,
+            unknown_locations: row.get("unknown_locations"),
+            cycle_count_ids: serde_json::from_value(row.get("cycle_count_ids"))?,
+            created_by: row.get("created_by"),
+            created_at: row.get("created_at"),
+            updated_at: row.get("updated_at"),
+            submitted_by: row.get("submitted_by"),
+            submitted_at: row.get("submitted_at"),
+        })
+    }
+}
+
+#[async_trait]
+impl StocktakeRepository for PostgresStocktakeRepository {
+    async fn create_session(&self, session: &StocktakeSession) -> Result<()> {
+        ensure_actor(session.created_by)?;
+        sqlx::query(
+            r#"
+            INSERT INTO public.stocktake_sessions (
+                id, tenant_id, name, snapshot_at, status, lines, unmatched_barcodes,
+                unknown_locations, cycle_count_ids, created_by, created_at, updated_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+            "#,
+        )
+        .bind(session.id)
+        .bind(session.tenant_id)
+        .bind(&session.name)
+        .bind(session.snapshot_at)
+        .bind(session.status.as_str())
+        .bind(serde_json::to_value(&session.lines)?)
+        .bind(serde_json::to_value(&session.unmatched_barcodes)?)
+        .bind(&session.unknown_locations)
+        .bind(serde_json::to_value(&session.cycle_count_ids)?)
+        .bind(session.created_by)
+        .bind(session.created_at)
+        .bind(session.updated_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn get_session(&self, tenant_id: Uuid, session_id: Uuid) -> Result<Option<StocktakeSession>> {
+        let row = sqlx::query(
+            r#"
+            SELECT id, tenant_id, name, snapshot_at, status, lines, unmatched_barcodes, unknown_locations,
+                   cycle_count_ids, created_by, created_at, updated_at, submitted_by, submitted_at
+            FROM public.stocktake_sessions
+            WHERE tenant_id = $1 AND id = $2
+            "#,
+        )
+        .bind(tenant_id)
+        .bind(session_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.as_ref().map(Self::map_session).transpose()
+    }
+
+    async fn replace_file_lines(
+        &self,
+        tenant_id: Uuid,
+        session_id: Uuid,
+        file_name: &str,
+        lines: &[ScanLine],
+    ) -> Result<FileLineChanges> {
+        let mut tx = self.pool.begin().await?;
+        let checksums: Vec<String> = lines.iter().map(|line| line.checksum.clone()).collect();
+
+        let removed = sqlx::query(
+            r#"
+            DELETE FROM public.stocktake_scan_lines
+            WHERE tenant_id = $1 AND session_id = $2 AND file_name = $3 AND NOT (checksum = ANY($4))
+            "#,
+        )
+        .bind(tenant_id)
+        .bind(session_id)
+        .bind(file_name)
+        .bind(&checksums)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        let mut added = 0;
+        for line in lines {
+            let inserted = sqlx::query(
+                r#"
+                INSERT INTO public.stocktake_scan_lines (
+                    id, tenant_id, session_id, file_name, line_number, location_code, barcode,
+                    counted_quantity, counter_id, scanned_at, checksum
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+                ON CONFLICT (session_id, file_name, checksum) DO UPDATE SET line_number = EXCLUDED.line_number
+                RETURNING (xmax = 0) AS inserted
+                "#,
+            )
+            .bind(Uuid::new_v4())
+            .bind(tenant_id)
+            .bind(session_id)
+            .bind(file_name)
+            .bind(line.line_number as i32)
+            .bind(&line.location_code)
+            .bind(&line.barcode)
+            .bind(line.counted_quantity)
+            .bind(&line.counter_id)
+            .bind(line.scanned_at)
+            .bind(&line.checksum)
+            .fetch_one(&mut *tx)
+            .await?;
+
+            if inserted.get::<bool, _>("inserted") {
+                added += 1;
+            }
+        }
+
+        tx.commit().await?;
+
+        Ok(FileLineChanges {
+            added,
+            unchanged: lines.len() as i32 - added,
+            removed: removed as i32,
+        })
+    }
+
+    async fn get_scan_lines(&self, tenant_id: Uuid, session_id: Uuid) -> Result<Vec<ScanLine>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT file_name, line_number, location_code, barcode, counted_quantity, counter_id, scanned_at, checksum
+            FROM public.stocktake_scan_lines
+            WHERE tenant_id = $1 AND session_id = $2
+            ORDER BY file_name, line_number
+            "#,
+        )
+        .bind(tenant_id)
+        .bind(session_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| ScanLine {
+                file_name: row.get("file_name"),
+                line_number: row.get::<i32, _>("line_number") as usize,
+                location_code: row.get("location_code"),
+                barcode: row.get("barcode"),
+                counted_quantity: row.get("counted_quantity"),
+                counter_id: row.get("counter_id"),
+                scanned_at: row.get("scanned_at"),
+                checksum: row.get("checksum"),
+            })
+            .collect())
+    }
+
+    async fn find_products_by_barcode(&self, tenant_id: Uuid, barcodes: &[String]) -> Result<HashMap<String, CountedProduct>> {
+        let rows = sqlx::query(
+            "SELECT id, barcode, cost_price FROM products WHERE tenant_id = $1 AND barcode = ANY($2)",
+        )
+        .bind(tenant_id)
+        .bind(barcodes)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| {
+                let cost_cents: Option<i64> = row.get("cost_price");
+                (
+                    row.get("barcode"),
+                    CountedProduct {
+                        product_id: row.get("id"),
+                        unit_cost: cost_cents.unwrap_or(0) as f64 / 100.0,
+                    },
+                )
+            })
+            .collect())
+    }
+
+    async fn find_locations_by_code(&self, tenant_id: Uuid, codes: &[String]) -> Result<HashMap<String, Uuid>> {
+        let rows = sqlx::query("SELECT id, code FROM locations WHERE tenant_id = $1 AND code = ANY($2)")
+            .bind(tenant_id)
+            .bind(codes)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows.iter().map(|row| (row.get("code"), row.get("id"))).collect())
+    }
+
+    async fn get_stock_positions(
+        &self,
+        pairs: &[(Uuid, Uuid)],
+        snapshot_at: DateTime<Utc>,
+    ) -> Result<HashMap<(Uuid, Uuid), StockPosition>> {
+        let (product_ids, location_ids): (Vec<Uuid>, Vec<Uuid>) = pairs.iter().copied().unzip();
+
+        let rows = sqlx::query(
+            r#"
+            SELECT p.product_id, p.location_id,
+                   COALESCE(li.quantity_available, 0) AS current_quantity,
+                   COALESCE((
+                       SELECT SUM(t.quantity_change)
+                       FROM inventory_transactions t
+                       WHERE t.product_id = p.product_id
+                         AND t.location_id = p.location_id
+                         AND t.transaction_date > $3
+                   ), 0)::INT AS change_since_snapshot
+            FROM UNNEST($1::uuid[], $2::uuid[]) AS p(product_id, location_id)
+            LEFT JOIN location_items li ON li.product_id = p.product_id AND li.location_id = p.location_id
+            "#,
+        )
+        .bind(&product_ids)
+        .bind(&location_ids)
+        .bind(snapshot_at)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| {
+                (
+                    (row.get("product_id"), row.get("location_id")),
+                    StockPosition {
+                        current_quantity: row.get("current_quantity"),
+                        change_since_snapshot: row.get("change_since_snapshot"),
+                    },
+                )
+            })
+            .collect())
+    }
+
+    async fn update_session(&self, session: &StocktakeSession) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE public.stocktake_sessions
+            SET status = $3, lines = $4, unmatched_barcodes = $5, unknown_locations = $6,
+                cycle_count_ids = $7, updated_at = $8, submitted_by = $9, submitted_at = $10
+            WHERE tenant_id = $1 AND id = $2
+            "#,
+        )
+        .bind(session.tenant_id)
+        .bind(session.id)
+        .bind(session.status.as_str())
+        .bind(serde_json::to_value(&session.lines)?)
+        .bind(serde_json::to_value(&session.unmatched_barcodes)?)
+        .bind(&session.unknown_locations)
+        .bind(serde_json::to_value(&session.cycle_count_ids)?)
+        .bind(session.updated_at)
+        .bind(session.submitted_by)
+        .bind(session.submitted_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
+
+/// A return order change and the stock movements it causes, applied together
+#[derive(Debug, Clone)]
+pub struct ReturnOrderChange {
+    pub order: ReturnOrder,
+    /// Status the stored order must still be in; concurrent changes fail the whole change
+    pub expected_status: ReturnStatus,
+    pub movements: Vec<InventoryMovement>,
+}
+
+/// Persistence for customer return orders
+#[async_trait]
+pub trait ReturnOrderRepository: Send + Sync {
+    async fn create_return_order(&self, order: &ReturnOrder) -> Result<()>;
+    async fn get_return_order(&self, tenant_id: Uuid, return_id: Uuid) -> Result<Option<ReturnOrder>>;
+    async fn list_return_orders(
+        &self,
+        tenant_id: Uuid,
+        status: Option<ReturnStatus>,
+        customer_id: Option<Uuid>,
+    ) -> Result<Vec<ReturnOrder>>;
+    /// Location type of each of the given locations that exists
+    async fn get_location_types(&self, tenant_id: Uuid, location_ids: &[Uuid]) -> Result<HashMap<Uuid, String>>;
+    async fn apply_return_change(&self, change: ReturnOrderChange) -> Result<()>;
+    /// Units received back per product and reason within the period
+    async fn get_returned_quantities(
+        &self,
+        tenant_id: Uuid,
+        period_start: DateTime<Utc>,
+        period_end: DateTime<Utc>,
+    ) -> Result<Vec<(Uuid, ReturnReason, i64)>>;
+    /// Units shipped to customers per product within the period; return-to-supplier shipments are excluded
+    async fn get_shipped_quantities(
+        &self,
+        product_ids: &[Uuid],
+        period_start: DateTime<Utc>,
+        period_end: DateTime<Utc>,
+    ) -> Result<HashMap<Uuid, i64>>;
+}
+
+pub struct PostgresReturnOrderRepository {
+    pool: Pool<Postgres>,
+}
+
+const RETURN_ORDER_COLUMNS: &str = "id, tenant_id, return_number, customer_id, order_id, status, \
+    quarantine_location_id, notes, created_by, created_at, updated_at, received_by, received_at, completed_at";
+
+const RETURN_LINE_COLUMNS: &str = "id, return_order_id, product_id, quantity, reason, note, disposition, \
+    target_location_id, supplier_id, disposition_note, inspected_by, inspected_at";
+
+impl PostgresReturnOrderRepository {
+    pub fn new(pool: Pool<Postgres>) -> Self {
+        Self { pool }
+    }
+
+    fn map_order(row: &sqlx::postgres::PgRow) -> Result<ReturnOrder> {
+        let status: String = row.get("status");
+        Ok(ReturnOrder {
+            id: row.get("id"),
+            tenant_id: row.get("tenant_id"),
+            return_number: row.get("return_number"),
+            customer_id: row.get("customer_id"),
+            order_id: row.get("order_id"),
+            status: ReturnStatus::parse(&status)
+                .ok_or_else(|| MasterDataError::DatabaseError(format!("Unknown return status: {}", status)))?,
+            quarantine_location_id: row.get("quarantine_location_id"),
+            notes: row.get("notes"),
+            lines: Vec::new(),
+            created_by: row.get("created_by"),
+            created_at: row.get("created_at"),
+            updated_at: row.get("updated_at"),
+            received_by: row.get("received_by"),
+            received_at: row.get("received_at"),
+            completed_at: row.get("completed_at"),
+        })
+    }
+
+    fn map_line(row: &sqlx::postgres::PgRow) -> Result<ReturnOrderLine> {
+        let reason: String = row.get("reason");
+        let disposition: Option<String> = row.get("disposition");
+        Ok(ReturnOrderLine {
+            id: row.get("id"),
+            product_id: row.get("product_id"),
+            quantity: row.get("quantity"),
+            reason: ReturnReason::parse(&reason)
+                .ok_or_else(|| MasterDataError::DatabaseError(format!("Unknown return reason: {}", reason)))?,
+            note: row.get("note"),
+            disposition: disposition
+                .map(|value| {
+                    Disposition::parse(&value)
+                        .ok_or_else(|| MasterDataError::DatabaseError(format!("Unknown disposition: {}", value)))
+                })
+                .transpose()?,
+            target_location_id: row.get("target_location_id"),
+            supplier_id: row.get("supplier_id"),
+            disposition_note: row.get("disposition_note"),
+            inspected_by: row.get("inspected_by"),
+            inspected_at: row.get("inspected_at"),
+        })
+    }
+
+    /// Load the lines of the given orders, in the order they were entered
+    async fn attach_lines(&self, tenant_id: Uuid, orders: &mut [ReturnOrder]) -> Result<()> {
+        if orders.is_empty() {
+            return Ok(());
+        }
+        let ids: Vec<Uuid> = orders.iter().map(|order| order.id).collect();
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM public.return_order_lines WHERE tenant_id = $1 AND return_order_id = ANY($2) \
+             ORDER BY return_order_id, line_number",
+            RETURN_LINE_COLUMNS
+        ))
+        .bind(tenant_id)
+        .bind(&ids)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut lines: HashMap<Uuid, Vec<ReturnOrderLine>> = HashMap::new();
+        for row in &rows {
+            lines.entry(row.get("return_order_id")).or_default().push(Self::map_line(row)?);
+        }
+        for order in orders.iter_mut() {
+            order.lines = lines.remove(&order.id).unwrap_or_default();
+        }
+        Ok(())
+    }
+}
+
+fn map_catalog_entry(row: &sqlx::postgres::PgRow) -> Result<SupplierCatalogEntry> {
+    let price_breaks: sqlx::types::Json<Vec<PriceBreak>> = row.try_get("price_breaks")?;
+    Ok(SupplierCatalogEntry {
+        id: row.try_get("id")?,
+        tenant_id: row.try_get("tenant_id")?,
+        supplier_id: row.try_get("supplier_id")?,
+        product_id: row.try_get("product_id")?,
+        supplier_sku: row.try_get("supplier_sku")?,
+        currency: row.try_get("currency")?,
+        price_breaks: price_breaks.0,
+        min_order_quantity: row.try_get("min_order_quantity")?,
+        pack_size: row.try_get("pack_size")?,
+        lead_time_days: row.try_get("lead_time_days")?,
+        valid_from: row.try_get("valid_from")?,
+        valid_to: row.try_get("valid_to")?,
+        is_active: row.try_get("is_active")?,
+        created_at: row.try_get("created_at")?,
+        updated_at: row.try_get("updated_at")?,
+        created_by: row.try_get("created_by")?,
+        updated_by: row.try_get("updated_by")?,
+    })
+}
+
+fn map_costing_settings(chain: Option<Vec<String>>, strict_costing: Option<bool>) -> CostingSettings {
+    let defaults = CostingSettings::default();
+    CostingSettings {
+        cost_source_chain: chain
+            .map(|chain| chain.iter().filter_map(|source| CostSource::parse(source)).collect())
+            .unwrap_or(defaults.cost_source_chain),
+        strict_costing: strict_costing.unwrap_or(defaults.strict_costing),
+    }
+}
+
+/// The cost an inbound movement is stored with, taken from the tenant's cost
+/// source chain when the movement comes without one. `None` for movements
+/// that aren't costed.
+async fn resolve_movement_cost(conn: &mut PgConnection, movement: &InventoryMovement) -> Result<Option<ResolvedCost>> {
+    if !costing::needs_cost(movement) {
+        return Ok(None);
+    }
+    if let Some(unit_cost) = movement.unit_cost {
+        return Ok(Some(ResolvedCost::provided(unit_cost)));
+    }
+    let (Some(product_id), Some(location_id), Some(quantity)) = (movement.product_id, movement.location_id, movement.quantity)
+    else {
+        return Ok(None);
+    };
+
+    let Some(product) = sqlx::query(
+        "SELECT p.tenant_id, p.currency, p.cost_price, s.cost_source_chain, s.strict_costing \
+         FROM products p LEFT JOIN inventory_costing_settings s ON s.tenant_id = p.tenant_id \
+         WHERE p.id = $1",
+    )
+    .bind(product_id)
+    .fetch_optional(&mut *conn)
+    .await?
+    else {
+        return Ok(None);
+    };
+    let tenant_id: Uuid = product.try_get("tenant_id")?;
+    let currency: String = product.try_get("currency")?;
+    let settings = map_costing_settings(product.try_get("cost_source_chain")?, product.try_get("strict_costing")?);
+
+    let mut candidates = CostCandidates::default();
+    for source in &settings.cost_source_chain {
+        match source {
+            CostSource::PurchaseOrderLine => {
+                let (Some("purchase_order"), Some(order_number)) =
+                    (movement.reference_document.as_deref(), movement.reference_number.as_deref())
+                else {
+                    continue;
+                };
+                candidates.purchase_order_line = sqlx::query_scalar(
+                    "SELECT ROUND(pol.unit_price::numeric, 4) FROM purchase_order_lines pol \
+                     JOIN purchase_orders po ON po.id = pol.purchase_order_id \
+                     WHERE po.order_number = $1 AND po.location_id = $2 AND pol.product_id = $3 AND pol.unit_price > 0 \
+                     ORDER BY pol.id LIMIT 1",
+                )
+                .bind(order_number)
+                .bind(location_id)
+                .bind(product_id)
+                .fetch_optional(&mut *conn)
+                .await?;
+            }
+            CostSource::SupplierCatalog => {
+                let rows = sqlx::query(
+                    "SELECT * FROM supplier_catalog_entries WHERE tenant_id = $1 AND product_id = $2 AND currency = $3",
+                )
+                .bind(tenant_id)
+                .bind(product_id)
+                .bind(&currency)
+                .fetch_all(&mut *conn)
+                .await?;
+                let entries = rows.iter().map(map_catalog_entry).collect::<Result<Vec<_>>>()?;
+                let request = CatalogQuoteRequest {
+                    quantity,
+                    as_of: movement.effective_date,
+                    max_lead_time_days: None,
+                    supplier_override: None,
+                };
+                candidates.supplier_catalog = select_best_entry(&entries, &request).map(|quote| costing::from_cents(quote.unit_price));
+            }
+            CostSource::ProductCostPrice => {
+                let cost_price: Option<i64> = product.try_get("cost_price")?;
+                candidates.product_cost_price = cost_price.filter(|cents| *cents > 0).map(costing::from_cents);
+            }
+            CostSource::LastReceipt => {
+                // Guesses from earlier receipts aren't passed on
+                candidates.last_receipt = sqlx::query_scalar(
+                    "SELECT t.unit_cost FROM inventory_transactions t \
+                     WHERE t.product_id = $1 AND t.quantity_change > 0 AND t.unit_cost IS NOT NULL \
+                       AND t.reversal_of IS NULL AND t.cost_source IS DISTINCT FROM 'last_receipt' \
+                       AND NOT EXISTS (SELECT 1 FROM inventory_transactions r WHERE r.reversal_of = t.id) \
+                     ORDER BY t.transaction_date DESC, t.created_at DESC LIMIT 1",
+                )
+                .bind(product_id)
+                .fetch_optional(&mut *conn)
+                .await?;
+            }
+            CostSource::Provided | CostSource::Restated => {}
+        }
+    }
+
+    costing::resolve_unit_cost(None, &candidates, &settings, product_id)
+}
+
+/// Record a movement and apply it to the location quantity. Incoming stock
+/// creates the inventory record when the product is new at the location.
+async fn apply_stock_movement(tx: &mut sqlx::Transaction<'_, Postgres>, movement: &InventoryMovement) -> Result<()> {
+    let cost = resolve_movement_cost(tx, movement).await?;
+    sqlx::query(
+        r#"
+        INSERT INTO inventory_transactions (
+            id, transaction_number, transaction_type, product_id, location_id, quantity_change,
+            unit_cost, cost_source, cost_needs_review,
+            reference_document, reference_number, reason_code, created_by, created_at, transaction_date
+        )
+        VALUES ($1, CONCAT('TXN-', EXTRACT(EPOCH FROM NOW())), $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)
+        "#,
+    )
+    .bind(movement.id)
+    .bind(&movement.movement_type)
+    .bind(movement.product_id)
+    .bind(movement.location_id)
+    .bind(movement.quantity)
+    .bind(cost.map(|c| c.unit_cost).or(movement.unit_cost))
+    .bind(cost.map(|c| c.source.as_str()))
+    .bind(cost.is_some_and(|c| c.needs_review))
+    .bind(&movement.reference_document)
+    .bind(&movement.reference_number)
+    .bind(&movement.reason)
+    .bind(movement.operator_id)
+    .bind(movement.created_at)
+    .bind(movement.effective_date)
+    .execute(&mut **tx)
+    .await?;
+
+    let updated = sqlx::query(
+        r#"
+        UPDATE location_items
+        SET quantity_available = quantity_available + $3, updated_at = NOW()
+        WHERE product_id = $1 AND location_id = $2 AND quantity_available + $3 >= 0
+        "#,
+    )
+    .bind(movement.product_id)
+    .bind(movement.location_id)
+    .bind(movement.quantity)
+    .execute(&mut **tx)
+    .await?;
+
+    if updated.rows_affected() > 0 {
+        return Ok(());
+    }
+
+    let inserted = if movement.quantity.unwrap_or(0) > 0 {
+        sqlx::query(
+            r#"
+            INSERT INTO location_items (
+                id, product_id, location_id, location_name, location_type, quantity_available, created_at, updated_at
+            )
+            SELECT $1, $2, l.id, l.name, l.location_type, $4, NOW(), NOW()
+            FROM locations l
+            WHERE l.id = $3
+              AND NOT EXISTS (SELECT 1 FROM location_items WHERE product_id = $2 AND location_id = $3)
+            "#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(movement.product_id)
+        .bind(movement.location_id)
+        .bind(movement.quantity)
+        .execute(&mut **tx)
+        .await?
+        .rows_affected()
+    } else {
+        0
+    };
+
+    if inserted == 0 {
+        return Err(MasterDataError::ValidationError {
+            field: "location_id".to_string(),
+            message: "No inventory record with enough stock for this product at the location".to_string(),
+        });
+    }
+    Ok(())
+}
+
+#[async_trait]
+impl ReturnOrderRepository for PostgresReturnOrderRepository {
+    async fn create_return_order(&self, order: &ReturnOrder) -> Result<()> {
+        ensure_actor(order.created_by)?;
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(&format!(
+            "INSERT INTO public.return_orders ({}) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)",
+            RETURN_ORDER_COLUMNS
+        ))
+        .bind(order.id)
+        .bind(order.tenant_id)
+        .bind(&order.return_number)
+        .bind(order.customer_id)
+        .bind(order.order_id)
+        .bind(order.status.as_str())
+        .bind(order.quarantine_location_id)
+        .bind(&order.notes)
+        .bind(order.created_by)
+        .bind(order.created_at)
+        .bind(order.updated_at)
+        .bind(order.received_by)
+        .bind(order.received_at)
+        .bind(order.completed_at)
+        .execute(&mut *tx)
+        .await?;
+
+        for (index, line) in order.lines.iter().enumerate() {
+            sqlx::query(&format!(
+                "INSERT INTO public.return_order_lines (tenant_id, line_number, {}) \
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)",
+                RETURN_LINE_COLUMNS
+            ))
+            .bind(order.tenant_id)
+            .bind(index as i32 + 1)
+            .bind(line.id)
+            .bind(order.id)
+            .bind(line.product_id)
+            .bind(line.quantity)
+            .bind(line.reason.as_str())
+            .bind(&line.note)
+            .bind(line.disposition.map(Disposition::as_str))
+            .bind(line.target_location_id)
+            .bind(line.supplier_id)
+            .bind(&line.disposition_note)
+            .bind(line.inspected_by)
+            .bind(line.inspected_at)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn get_return_order(&self, tenant_id: Uuid, return_id: Uuid) -> Result<Option<ReturnOrder>> {
+        let row = sqlx::query(&format!(
+            "SELECT {} FROM public.return_orders WHERE tenant_id = $1 AND id = $2",
+            RETURN_ORDER_COLUMNS
+        ))
+        .bind(tenant_id)
+        .bind(return_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        let mut orders = vec![Self::map_order(&row)?];
+        self.attach_lines(tenant_id, &mut orders).await?;
+        Ok(orders.pop())
+    }
+
+    async fn list_return_orders(
+        &self,
+        tenant_id: Uuid,
+        status: Option<ReturnStatus>,
+        customer_id: Option<Uuid>,
+    ) -> Result<Vec<ReturnOrder>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM public.return_orders \
+             WHERE tenant_id = $1 AND ($2::text IS NULL OR status = $2) AND ($3::uuid IS NULL OR customer_id = $3) \
+             ORDER BY created_at DESC",
+            RETURN_ORDER_COLUMNS
+        ))
+        .bind(tenant_id)
+        .bind(status.map(ReturnStatus::as_str))
+        .bind(customer_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut orders = rows.iter().map(Self::map_order).collect::<Result<Vec<_>>>()?;
+        self.attach_lines(tenant_id, &mut orders).await?;
+        Ok(orders)
+    }
+
+    async fn get_location_types(&self, tenant_id: Uuid, location_ids: &[Uuid]) -> Result<HashMap<Uuid, String>> {
+        let rows = sqlx::query("SELECT id, location_type::text AS location_type FROM locations WHERE tenant_id = $1 AND id = ANY($2)")
+            .bind(tenant_id)
+            .bind(location_ids)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows.iter().map(|row| (row.get("id"), row.get("location_type"))).collect())
+    }
+
+    async fn apply_return_change(&self, change: ReturnOrderChange) -> Result<()> {
+        let order = &change.order;
+        let mut tx = self.pool.begin().await?;
+
+        let updated = sqlx::query(
+            r#"
+            UPDATE public.return_orders
+            SET status = $3, quarantine_location_id = $4, updated_at = $5, received_by = $6,
+                received_at = $7, completed_at = $8
+            WHERE tenant_id = $1 AND id = $2 AND status = $9
+            "#,
+        )
+        .bind(order.tenant_id)
+        .bind(order.id)
+        .bind(order.status.as_str())
+        .bind(order.quarantine_location_id)
+        .bind(order.updated_at)
+        .bind(order.received_by)
+        .bind(order.received_at)
+        .bind(order.completed_at)
+        .bind(change.expected_status.as_str())
+        .execute(&mut *tx)
+        .await?;
+
+        if updated.rows_affected() == 0 {
+            return Err(MasterDataError::InvalidReturnTransition {
+                return_number: order.return_number.clone(),
+                from: change.expected_status.as_str().to_string(),
+                to: order.status.as_str().to_string(),
+            });
+        }
+
+        for line in &order.lines {
+            sqlx::query(
+                r#"
+                UPDATE public.return_order_lines
+                SET disposition = $3, target_location_id = $4, supplier_id = $5, disposition_note = $6,
+                    inspected_by = $7, inspected_at = $8
+                WHERE tenant_id = $1 AND id = $2
+                "#,
+            )
+            .bind(order.tenant_id)
+            .bind(line.id)
+            .bind(line.disposition.map(Disposition::as_str))
+            .bind(line.target_location_id)
+            .bind(line.supplier_id)
+            .bind(&line.disposition_note)
+            .bind(line.inspected_by)
+            .bind(line.inspected_at)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        for movement in &change.movements {
+            apply_stock_movement(&mut tx, movement).await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn get_returned_quantities(
+        &self,
+        tenant_id: Uuid,
+        period_start: DateTime<Utc>,
+        period_end: DateTime<Utc>,
+    ) -> Result<Vec<(Uuid, ReturnReason, i64)>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT l.product_id, l.reason, SUM(l.quantity)::BIGINT AS quantity
+            FROM public.return_order_lines l
+            JOIN public.return_orders o ON o.id = l.return_order_id AND o.tenant_id = l.tenant_id
+            WHERE o.tenant_id = $1 AND o.received_at >= $2 AND o.received_at < $3
+            GROUP BY l.product_id, l.reason
+            "#,
+        )
+        .bind(tenant_id)
+        .bind(period_start)
+        .bind(period_end)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                let reason: String = row.get("reason");
+                Ok((
+                    row.get("product_id"),
+                    ReturnReason::parse(&reason)
+                        .ok_or_else(|| MasterDataError::DatabaseError(format!("Unknown return reason: {}", reason)))?,
+                    row.get("quantity"),
+                ))
+            })
+            .collect()
+    }
+
+    async fn get_shipped_quantities(
+        &self,
+        product_ids: &[Uuid],
+        period_start: DateTime<Utc>,
+        period_end: DateTime<Utc>,
+    ) -> Result<HashMap<Uuid, i64>> {
+        if product_ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let rows = sqlx::query(
+            r#"
+            SELECT product_id, -SUM(quantity_change)::BIGINT AS quantity
+            FROM inventory_transactions
+            WHERE product_id = ANY($1)
+              AND transaction_type = 'shipment'
+              AND transaction_date >= $2 AND transaction_date < $3
+              AND COALESCE(reference_number, '') NOT LIKE 'RMA-%'
+            GROUP BY product_id
+            "#,
+        )
+        .bind(product_ids)
+        .bind(period_start)
+        .bind(period_end)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(|row| (row.get("product_id"), row.get("quantity"))).collect())
+    }
+}
+
+/// Saved forecast scenarios and the supply and demand figures they are projected from
+#[async_trait]
+pub trait ForecastScenarioRepository: Send + Sync {
+    async fn save_scenario(&self, scenario: &ForecastScenario) -> Result<()>;
+    async fn get_scenario(&self, tenant_id: Uuid, scenario_id: Uuid) -> Result<Option<ForecastScenario>>;
+    /// Most recent first
+    async fn list_scenarios(&self, tenant_id: Uuid, limit: i64) -> Result<Vec<ForecastScenario>>;
+    /// Outstanding quantities of open purchase orders and in-transit transfers into the given pairs
+    async fn get_incoming_supply(&self, pairs: &[(Uuid, Uuid)]) -> Result<Vec<IncomingSupply>>;
+    /// Units shipped per day from `from` through `to`
+    async fn get_daily_demand(
+        &self,
+        product_id: Uuid,
+        location_id: Uuid,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<HashMap<NaiveDate, f64>>;
+}
+
+pub struct PostgresForecastScenarioRepository {
+    pool: Pool<Postgres>,
+}
+
+impl PostgresForecastScenarioRepository {
+    pub fn new(pool: Pool<Postgres>) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl ForecastScenarioRepository for PostgresForecastScenarioRepository {
+    async fn save_scenario(&self, scenario: &ForecastScenario) -> Result<()> {
+        scenario.created_by.map(ensure_actor).transpose()?;
+        sqlx::query(
+            r#"
+            INSERT INTO public.forecast_scenarios (id, tenant_id, name, start_date, end_date, scenario, created_by, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            "#,
+        )
+        .bind(scenario.id)
+        .bind(scenario.tenant_id)
+        .bind(&scenario.name)
+        .bind(scenario.start_date)
+        .bind(scenario.end_date)
+        .bind(serde_json::to_value(scenario)?)
+        .bind(scenario.created_by)
+        .bind(scenario.created_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn get_scenario(&self, tenant_id: Uuid, scenario_id: Uuid) -> Result<Option<ForecastScenario>> {
+        let row = sqlx::query("SELECT scenario FROM public.forecast_scenarios WHERE tenant_id = $1 AND id = $2")
+            .bind(tenant_id)
+            .bind(scenario_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        row.map(|row| serde_json::from_value(row.get("scenario")).map_err(MasterDataError::from))
+            .transpose()
+    }
+
+    async fn list_scenarios(&self, tenant_id: Uuid, limit: i64) -> Result<Vec<ForecastScenario>> {
+        let rows = sqlx::query(
+            "SELECT scenario FROM public.forecast_scenarios WHERE tenant_id = $1 ORDER BY created_at DESC LIMIT $2",
+        )
+        .bind(tenant_id)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| serde_json::from_value(row.get("scenario")).map_err(MasterDataError::from))
+            .collect()
+    }
+
+    async fn get_incoming_supply(&self, pairs: &[(Uuid, Uuid)]) -> Result<Vec<IncomingSupply>> {
+        if pairs.is_empty() {
+            return Ok(Vec::new());
+        }
+        let (product_ids, location_ids): (Vec<Uuid>, Vec<Uuid>) = pairs.iter().copied().unzip();
+
+        // Transfers without an expected arrival count from the first projected day
+        let rows = sqlx::query(
+            r#"
+            SELECT 'purchase_order' AS source, po.id AS reference_id, pol.product_id, po.location_id,
+                   (pol.quantity_ordered - pol.quantity_received)::float8 AS quantity,
+                   po.expected_delivery_date::date AS expected_date
+            FROM purchase_orders po
+            JOIN purchase_order_lines pol ON pol.purchase_order_id = po.id
+            JOIN UNNEST($1::uuid[], $2::uuid[]) AS wanted(product_id, location_id)
+              ON pol.product_id = wanted.product_id AND po.location_id = wanted.location_id
+            WHERE po.status::text IN ('submitted', 'approved', 'ordered', 'partially_received', 'pending')
+              AND pol.quantity_ordered > pol.quantity_received
+            UNION ALL
+            SELECT 'transfer', st.id, st.product_id, st.to_location_id,
+                   (COALESCE(st.quantity_shipped, st.quantity) - COALESCE(st.quantity_received, 0))::float8,
+                   st.expected_arrival_date
+            FROM stock_transfers st
+            JOIN UNNEST($1::uuid[], $2::uuid[]) AS wanted(product_id, location_id)
+              ON st.product_id = wanted.product_id AND st.to_location_id = wanted.location_id
+            WHERE st.status::text IN ('in_transit', 'partially_received')
+              AND COALESCE(st.quantity_shipped, st.quantity) > COALESCE(st.quantity_received, 0)
+            "#,
+        )
+        .bind(&product_ids)
+        .bind(&location_ids)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                let source: String = row.try_get("source")?;
+                Ok(IncomingSupply {
+                    source: if source == "transfer" { SupplySource::Transfer } else { SupplySource::PurchaseOrder },
+                    reference_id: row.try_get("reference_id")?,
+                    product_id: row.try_get("product_id")?,
+                    location_id: row.try_get("location_id")?,
+                    quantity: row.try_get("quantity")?,
+                    expected_date: row.try_get("expected_date")?,
+                })
+            })
+            .collect()
+    }
+
+    async fn get_daily_demand(
+        &self,
+        product_id: Uuid,
+        location_id: Uuid,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<HashMap<NaiveDate, f64>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT transaction_date::date AS day, (-SUM(quantity_change))::float8 AS quantity
+            FROM inventory_transactions
+            WHERE product_id = $1 AND location_id = $2
+              AND transaction_type = 'shipment'
+              AND transaction_date::date BETWEEN $3 AND $4
+            GROUP BY transaction_date::date
+            "#,
+        )
+        .bind(product_id)
+        .bind(location_id)
+        .bind(from)
+        .bind(to)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| Ok((row.try_get("day")?, row.try_get("quantity")?)))
+            .collect()
+    }
+}
+
+/// Persistence for stock transfers, their approval rules and approvals
+#[async_trait]
+pub trait StockTransferRepository: Send + Sync {
+    /// Unit cost of the product and the regions of both locations; fails if a location is not the tenant's
+    async fn get_transfer_facts(
+        &self,
+        tenant_id: Uuid,
+        product_id: Uuid,
+        from_location_id: Uuid,
+        to_location_id: Uuid,
+        quantity: i32,
+    ) -> Result<TransferFacts>;
+    async fn get_approval_rules(&self, tenant_id: Uuid) -> Result<Vec<TransferApprovalRule>>;
+    async fn save_approval_rules(&self, tenant_id: Uuid, rules: &[TransferApprovalRule], updated_by: Uuid) -> Result<()>;
+    /// Store a new transfer, its approval and the stock reservation together
+    async fn create_transfer(&self, record: &TransferRecord, reservation: ReservationChange) -> Result<()>;
+    async fn get_transfer(&self, tenant_id: Uuid, transfer_id: Uuid) -> Result<Option<TransferRecord>>;
+    /// Most recently requested first
+    async fn list_transfers(&self, tenant_id: Uuid, filter: &TransferListFilter) -> Result<Vec<TransferRecord>>;
+    /// Store an approval decision; fails if the transfer no longer waits for approval
+    async fn apply_decision(&self, record: &TransferRecord, release: Option<ReservationChange>) -> Result<()>;
+    /// Undecided approvals of all tenants requested before `requested_before` and not yet escalated
+    async fn list_overdue_approvals(&self, requested_before: DateTime<Utc>) -> Result<Vec<TransferApproval>>;
+    async fn mark_escalated(&self, tenant_id: Uuid, transfer_id: Uuid, escalated_at: DateTime<Utc>) -> Result<()>;
+}
+
+pub struct PostgresStockTransferRepository {
+    pool: Pool<Postgres>,
+}
+
+const STOCK_TRANSFER_COLUMNS: &str = "st.id, st.product_id, st.from_location_id, st.to_location_id, st.quantity, \
+    st.quantity_shipped, st.quantity_received, st.status, st.priority, st.reason, st.requested_by, st.approved_by, \
+    st.shipped_by, st.received_by, st.requested_date, st.approved_date, st.shipped_date, st.received_date, \
+    st.actual_delivery_date, st.expected_arrival_date, st.tracking_number, st.carrier, st.shipping_cost, st.notes, \
+    st.created_at, st.created_by";
+
+const TRANSFER_APPROVAL_COLUMNS: &str = "ta.transfer_id AS approval_transfer_id, ta.tenant_id AS approval_tenant_id, \
+    ta.reasons, ta.transfer_value, ta.requested_by AS approval_requested_by, ta.requested_at AS approval_requested_at, \
+    ta.escalated_at, ta.decision, ta.decided_by, ta.decided_at, ta.comment AS approval_comment";
+
+impl PostgresStockTransferRepository {
+    pub fn new(pool: Pool<Postgres>) -> Self {
+        Self { pool }
+    }
+
+    fn map_approval(row: &sqlx::postgres::PgRow) -> Result<Option<TransferApproval>> {
+        let Some(transfer_id) = row.try_get::<Option<Uuid>, _>("approval_transfer_id")? else {
+            return Ok(None);
+        };
+        let decision: Option<String> = row.try_get("decision")?;
+        Ok(Some(TransferApproval {
+            transfer_id,
+            tenant_id: row.try_get("approval_tenant_id")?,
+            reasons: row.try_get("reasons")?,
+            transfer_value: row.try_get("transfer_value")?,
+            requested_by: row.try_get("approval_requested_by")?,
+            requested_at: row.try_get("approval_requested_at")?,
+            escalated_at: row.try_get("escalated_at")?,
+            decision: decision
+                .map(|value| {
+                    ApprovalDecision::parse(&value)
+                        .ok_or_else(|| MasterDataError::DatabaseError(format!("Unknown approval decision: {}", value)))
+                })
+                .transpose()?,
+            decided_by: row.try_get("decided_by")?,
+            decided_at: row.try_get("decided_at")?,
+            comment: row.try_get("approval_comment")?,
+        }))
+    }
+
+    fn map_record(row: &sqlx::postgres::PgRow) -> Result<TransferRecord> {
+        Ok(TransferRecord {
+            transfer: StockTransfer::from_row(row)?,
+            approval: Self::map_approval(row)?,
+        })
+    }
+
+    /// Reserve (positive) or release (negative) stock at the source location
+    async fn change_reservation(tx: &mut sqlx::Transaction<'_, Postgres>, change: ReservationChange) -> Result<()> {
+        let updated = sqlx::query(
+            r#"
+            UPDATE location_items
+            SET quantity_reserved = GREATEST(quantity_reserved + $3, 0), updated_at = NOW()
+            WHERE product_id = $1 AND location_id = $2
+              AND ($3 < 0 OR quantity_available - quantity_reserved >= $3)
+            "#,
+        )
+        .bind(change.product_id)
+        .bind(change.location_id)
+        .bind(change.quantity)
+        .execute(&mut **tx)
+        .await?;
+
+        if updated.rows_affected() == 0 && change.quantity > 0 {
+            return Err(MasterDataError::ValidationError {
+                field: "quantity".to_string(),
+                message: "Insufficient unreserved inventory for transfer".to_string(),
+            });
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl StockTransferRepository for PostgresStockTransferRepository {
+    async fn get_transfer_facts(
+        &self,
+        tenant_id: Uuid,
+        product_id: Uuid,
+        from_location_id: Uuid,
+        to_location_id: Uuid,
+        quantity: i32,
+    ) -> Result<TransferFacts> {
+        let row = sqlx::query(
+            r#"
+            SELECT fl.region AS from_region, tl.region AS to_region,
+                   (SELECT p.cost_price FROM products p WHERE p.tenant_id = $1 AND p.id = $2) AS cost_price
+            FROM locations fl
+            JOIN locations tl ON tl.tenant_id = $1 AND tl.id = $4
+            WHERE fl.tenant_id = $1 AND fl.id = $3
+            "#,
+        )
+        .bind(tenant_id)
+        .bind(product_id)
+        .bind(from_location_id)
+        .bind(to_location_id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or_else(|| {
+            MasterDataError::NotFoundError(format!("Location {} or {}", from_location_id, to_location_id))
+        })?;
+
+        let cost_cents: Option<i64> = row.try_get("cost_price")?;
+        Ok(TransferFacts {
+            quantity,
+            unit_cost: cost_cents.unwrap_or(0) as f64 / 100.0,
+            from_region: row.try_get("from_region")?,
+            to_region: row.try_get("to_region")?,
+        })
+    }
+
+    async fn get_approval_rules(&self, tenant_id: Uuid) -> Result<Vec<TransferApprovalRule>> {
+        let rules: Option<serde_json::Value> =
+            sqlx::query_scalar("SELECT rules FROM public.transfer_approval_rules WHERE tenant_id = $1")
+                .bind(tenant_id)
+                .fetch_optional(&self.pool)
+                .await?;
+
+        match rules {
+            Some(rules) => Ok(serde_json::from_value(rules)?),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    async fn save_approval_rules(&self, tenant_id: Uuid, rules: &[TransferApprovalRule], updated_by: Uuid) -> Result<()> {
+        ensure_actor(updated_by)?;
+        sqlx::query(
+            r#"
+            INSERT INTO public.transfer_approval_rules (tenant_id, rules, updated_by, updated_at)
+            VALUES ($1, $2, $3, NOW())
+            ON CONFLICT (tenant_id) DO UPDATE
+            SET rules = EXCLUDED.rules, updated_by = EXCLUDED.updated_by, updated_at = EXCLUDED.updated_at
+            "#,
+        )
+        .bind(tenant_id)
+        .bind(serde_json::to_value(rules)?)
+        .bind(updated_by)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn create_transfer(&self, record: &TransferRecord, reservation: ReservationChange) -> Result<()> {
+        ensure_actor(record.transfer.requested_by)?;
+        let transfer = &record.transfer;
+        let mut tx = self.pool.begin().await?;
+
+        Self::change_reservation(&mut tx, reservation).await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO stock_transfers (
+                id, product_id, from_location_id, to_location_id, quantity, quantity_shipped, quantity_received,
+                status, priority, reason, requested_by, approved_by, shipped_by, received_by, requested_date,
+                approved_date, shipped_date, received_date, actual_delivery_date, tracking_number, carrier,
+                shipping_cost, notes, created_at, created_by, expected_arrival_date
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20,
+                    $21, $22, $23, $24, $25, $26)
+            "#,
+        )
+        .bind(transfer.id)
+        .bind(transfer.product_id)
+        .bind(transfer.from_location_id)
+        .bind(transfer.to_location_id)
+        .bind(transfer.quantity)
+        .bind(transfer.quantity_shipped)
+        .bind(transfer.quantity_received)
+        .bind(&transfer.status)
+        .bind(&transfer.priority)
+        .bind(&transfer.reason)
+        .bind(transfer.requested_by)
+        .bind(transfer.approved_by)
+        .bind(transfer.shipped_by)
+        .bind(transfer.received_by)
+        .bind(transfer.requested_date)
+        .bind(transfer.approved_date)
+        .bind(transfer.shipped_date)
+        .bind(transfer.received_date)
+        .bind(transfer.actual_delivery_date)
+        .bind(&transfer.tracking_number)
+        .bind(&transfer.carrier)
+        .bind(transfer.shipping_cost)
+        .bind(&transfer.notes)
+        .bind(transfer.created_at)
+        .bind(transfer.created_by)
+        .bind(transfer.expected_arrival_date)
+        .execute(&mut *tx)
+        .await?;
+
+        if let Some(approval) = &record.approval {
+            sqlx::query(
+                r#"
+                INSERT INTO public.transfer_approvals (transfer_id, tenant_id, reasons, transfer_value, requested_by, requested_at)
+                VALUES ($1, $2, $3, $4, $5, $6)
+                "#,
+            )
+            .bind(approval.transfer_id)
+            .bind(approval.tenant_id)
+            .bind(&approval.reasons)
+            .bind(approval.transfer_value)
+            .bind(approval.requested_by)
+            .bind(approval.requested_at)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn get_transfer(&self, tenant_id: Uuid, transfer_id: Uuid) -> Result<Option<TransferRecord>> {
+        let row = sqlx::query(&format!(
+            "SELECT {}, {} FROM stock_transfers st \
+             JOIN locations l ON l.id = st.from_location_id \
+             LEFT JOIN public.transfer_approvals ta ON ta.transfer_id = st.id \
+             WHERE l.tenant_id = $1 AND st.id = $2",
+            STOCK_TRANSFER_COLUMNS, TRANSFER_APPROVAL_COLUMNS
+        ))
+        .bind(tenant_id)
+        .bind(transfer_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        row.as_ref().map(Self::map_record).transpose()
+    }
+
+    async fn list_transfers(&self, tenant_id: Uuid, filter: &TransferListFilter) -> Result<Vec<TransferRecord>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {}, {} FROM stock_transfers st \
+             JOIN locations l ON l.id = st.from_location_id \
+             LEFT JOIN public.transfer_approvals ta ON ta.transfer_id = st.id \
+             WHERE l.tenant_id = $1 AND ($2::text IS NULL OR st.status::text = $2) \
+               AND ($3::uuid IS NULL OR (st.status::text = 'pending_approval' AND ta.decision IS NULL \
+                    AND st.created_by <> $3 AND ta.requested_by <> $3)) \
+             ORDER BY st.requested_date DESC",
+            STOCK_TRANSFER_COLUMNS, TRANSFER_APPROVAL_COLUMNS
+        ))
+        .bind(tenant_id)
+        .bind(filter.status.as_ref().map(TransferStatus::as_str))
+        .bind(filter.awaiting_approval_by)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(Self::map_record).collect()
+    }
+
+    async fn apply_decision(&self, record: &TransferRecord, release: Option<ReservationChange>) -> Result<()> {
+        let (transfer, approval) = match &record.approval {
+            Some(approval) => (&record.transfer, approval),
+            None => {
+                return Err(MasterDataError::ValidationError {
+                    field: "transfer_id".to_string(),
+                    message: "Transfer does not need approval".to_string(),
+                })
+            }
+        };
+        let mut tx = self.pool.begin().await?;
+
+        let updated = sqlx::query(
+            r#"
+            UPDATE stock_transfers
+            SET status = $2, approved_by = $3, approved_date = $4
+            WHERE id = $1 AND status::text = 'pending_approval'
+            "#,
+        )
+        .bind(transfer.id)
+        .bind(&transfer.status)
+        .bind(transfer.approved_by)
+        .bind(transfer.approved_date)
+        .execute(&mut *tx)
+        .await?;
+
+        if updated.rows_affected() == 0 {
+            return Err(MasterDataError::InvalidTransferTransition {
+                transfer_id: transfer.id.to_string(),
+                from: TransferStatus::PendingApproval.as_str().to_string(),
+                to: transfer.status.as_str().to_string(),
+            });
+        }
+
+        sqlx::query(
+            r#"
+            UPDATE public.transfer_approvals
+            SET decision = $3, decided_by = $4, decided_at = $5, comment = $6
+            WHERE tenant_id = $1 AND transfer_id = $2 AND decision IS NULL
+            "#,
+        )
+        .bind(approval.tenant_id)
+        .bind(approval.transfer_id)
+        .bind(approval.decision.map(ApprovalDecision::as_str))
+        .bind(approval.decided_by)
+        .bind(approval.decided_at)
+        .bind(&approval.comment)
+        .execute(&mut *tx)
+        .await?;
+
+        if let Some(release) = release {
+            Self::change_reservation(&mut tx, release).await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn list_overdue_approvals(&self, requested_before: DateTime<Utc>) -> Result<Vec<TransferApproval>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM public.transfer_approvals ta \
+             WHERE ta.decision IS NULL AND ta.escalated_at IS NULL AND ta.requested_at <= $1 \
+             ORDER BY ta.requested_at",
+            TRANSFER_APPROVAL_COLUMNS
+        ))
+        .bind(requested_before)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .filter_map(|row| Self::map_approval(row).transpose())
+            .collect()
+    }
+
+    async fn mark_escalated(&self, tenant_id: Uuid, transfer_id: Uuid, escalated_at: DateTime<Utc>) -> Result<()> {
+        sqlx::query(
+            "UPDATE public.transfer_approvals SET escalated_at = $3 \
+             WHERE tenant_id = $1 AND transfer_id = $2 AND escalated_at IS NULL",
+        )
+        .bind(tenant_id)
+        .bind(transfer_id)
+        .bind(escalated_at)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
+
+/// Reconciliation of `location_items.quantity_reserved` with the stock held
+/// for each item; see [`reservation_drift`](crate::inventory::reservation_drift)
+#[async_trait]
+pub trait ReservationReconciliationRepository: Send + Sync {
+    /// Measure up to `limit` items of the scope, of one tenant or all, in item
+    /// id order after `after`; drifted or not
+    async fn measure_items(
+        &self,
+        tenant_id: Option<Uuid>,
+        scope: ReconciliationScope,
+        after: Option<Uuid>,
+        limit: i64,
+    ) -> Result<Vec<ReservationDrift>>;
+    /// Lock the item, measure it again and correct the column, storing the
+    /// adjustment in the same transaction
+    async fn repair_item(
+        &self,
+        tenant_id: Uuid,
+        location_item_id: Uuid,
+        run_id: Uuid,
+        corrected_by: Option<Uuid>,
+    ) -> Result<RepairOutcome>;
+    /// Corrections of the item made after `since`, most recent first
+    async fn item_adjustments(&self, location_item_id: Uuid, since: DateTime<Utc>) -> Result<Vec<ReservationAdjustment>>;
+    /// Most recent corrections of the tenant first
+    async fn list_adjustments(&self, tenant_id: Uuid, limit: i64) -> Result<Vec<ReservationAdjustment>>;
+}
+
+pub struct PostgresReservationReconciliationRepository {
+    pool: Pool<Postgres>,
+}
+
+/// Active reservations of item `li` plus what its unshipped transfers hold;
+/// `$1` is the list of holding transfer statuses
+const HELD_RESERVED: &str = "(COALESCE((SELECT SUM(r.reserved_quantity) FROM stock_reservations r \
+        WHERE r.location_item_id = li.id AND r.status = 'active'), 0) \
+    + COALESCE((SELECT SUM(st.quantity - COALESCE(st.quantity_shipped, 0)) FROM stock_transfers st \
+        WHERE st.product_id = li.product_id AND st.from_location_id = li.location_id \
+          AND st.status::text = ANY($1)), 0))::INTEGER";
+
+const ADJUSTMENT_COLUMNS: &str = "id, tenant_id, location_item_id, product_id, location_id, previous_reserved, \
+    corrected_reserved, run_id, corrected_by, corrected_at";
+
+impl PostgresReservationReconciliationRepository {
+    pub fn new(pool: Pool<Postgres>) -> Self {
+        Self { pool }
+    }
+
+    fn holding_statuses() -> Vec<String> {
+        HOLDING_TRANSFER_STATUSES.iter().map(|status| status.to_string()).collect()
+    }
+
+    fn map_drift(row: &sqlx::postgres::PgRow) -> Result<ReservationDrift> {
+        Ok(ReservationDrift {
+            tenant_id: row.try_get("tenant_id")?,
+            location_item_id: row.try_get("location_item_id")?,
+            product_id: row.try_get("product_id")?,
+            location_id: row.try_get("location_id")?,
+            quantity_available: row.try_get("quantity_available")?,
+            recorded_reserved: row.try_get("recorded_reserved")?,
+            held_reserved: row.try_get("held_reserved")?,
+        })
+    }
+
+    fn map_adjustment(row: &sqlx::postgres::PgRow) -> Result<ReservationAdjustment> {
+        Ok(ReservationAdjustment {
+            id: row.try_get("id")?,
+            tenant_id: row.try_get("tenant_id")?,
+            location_item_id: row.try_get("location_item_id")?,
+            product_id: row.try_get("product_id")?,
+            location_id: row.try_get("location_id")?,
+            previous_reserved: row.try_get("previous_reserved")?,
+            corrected_reserved: row.try_get("corrected_reserved")?,
+            run_id: row.try_get("run_id")?,
+            corrected_by: row.try_get("corrected_by")?,
+            corrected_at: row.try_get("corrected_at")?,
+        })
+    }
+}
+
+#[async_trait]
+impl ReservationReconciliationRepository for PostgresReservationReconciliationRepository {
+    async fn measure_items(
+        &self,
+        tenant_id: Option<Uuid>,
+        scope: ReconciliationScope,
+        after: Option<Uuid>,
+        limit: i64,
+    ) -> Result<Vec<ReservationDrift>> {
+        // The slice arithmetic matches reservation_drift::sweep_slice
+        let rows = sqlx::query(&format!(
+            "SELECT l.tenant_id, li.id AS location_item_id, li.product_id, li.location_id, li.quantity_available, \
+                 li.quantity_reserved AS recorded_reserved, {} AS held_reserved \
+             FROM location_items li JOIN locations l ON l.id = li.location_id \
+             WHERE ($2::uuid IS NULL OR l.tenant_id = $2) \
+               AND ($3::int IS NULL OR (get_byte(uuid_send(li.id), 14) * 256 + get_byte(uuid_send(li.id), 15)) % $4 = $3) \
+               AND ($5::uuid IS NULL OR li.id > $5) \
+             ORDER BY li.id LIMIT $6",
+            HELD_RESERVED
+        ))
+        .bind(Self::holding_statuses())
+        .bind(tenant_id)
+        .bind(scope.slice().map(|slice| slice as i32))
+        .bind(SWEEP_SLICES as i32)
+        .bind(after)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(Self::map_drift).collect()
+    }
+
+    async fn repair_item(
+        &self,
+        tenant_id: Uuid,
+        location_item_id: Uuid,
+        run_id: Uuid,
+        corrected_by: Option<Uuid>,
+    ) -> Result<RepairOutcome> {
+        let mut tx = self.pool.begin().await?;
+
+        // Lock before summing: a reservation committing meanwhile either is in
+        // the sum or increments the corrected column after us
+        let locked = sqlx::query(
+            "SELECT li.id FROM location_items li JOIN locations l ON l.id = li.location_id \
+             WHERE li.id = $1 AND l.tenant_id = $2 FOR UPDATE OF li",
+        )
+        .bind(location_item_id)
+        .bind(tenant_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+        if locked.is_none() {
+            return Err(MasterDataError::NotFoundError(format!("Location item {}", location_item_id)));
+        }
+
+        let row = sqlx::query(&format!(
+            "SELECT l.tenant_id, li.id AS location_item_id, li.product_id, li.location_id, li.quantity_available, \
+                 li.quantity_reserved AS recorded_reserved, {} AS held_reserved \
+             FROM location_items li JOIN locations l ON l.id = li.location_id \
+             WHERE li.id = $2",
+            HELD_RESERVED
+        ))
+        .bind(Self::holding_statuses())
+        .bind(location_item_id)
+        .fetch_one(&mut *tx)
+        .await?;
+        let measured = Self::map_drift(&row)?;
+
+        let outcome = plan_repair(&measured, run_id, corrected_by, Utc::now());
+        let RepairOutcome::Corrected { adjustment } = &outcome else {
+            tx.rollback().await?;
+            return Ok(outcome);
+        };
+
+        // Re-checks what plan_repair saw; the row is locked, so a miss is a bug
+        let updated = sqlx::query(
+            "UPDATE location_items SET quantity_reserved = $3, updated_at = NOW() \
+             WHERE id = $1 AND quantity_reserved = $2 AND $3 BETWEEN 0 AND quantity_available",
+        )
+        .bind(location_item_id)
+        .bind(adjustment.previous_reserved)
+        .bind(adjustment.corrected_reserved)
+        .execute(&mut *tx)
+        .await?;
+        if updated.rows_affected() == 0 {
+            return Err(MasterDataError::DatabaseError(format!(
+                "Location item {} changed while locked for reservation repair",
+                location_item_id
+            )));
+        }
+
+        sqlx::query(&format!(
+            "INSERT INTO public.reservation_adjustments ({}) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)",
+            ADJUSTMENT_COLUMNS
+        ))
+        .bind(adjustment.id)
+        .bind(adjustment.tenant_id)
+        .bind(adjustment.location_item_id)
+        .bind(adjustment.product_id)
+        .bind(adjustment.location_id)
+        .bind(adjustment.previous_reserved)
+        .bind(adjustment.corrected_reserved)
+        .bind(adjustment.run_id)
+        .bind(adjustment.corrected_by)
+        .bind(adjustment.corrected_at)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(outcome)
+    }
+
+    async fn item_adjustments(&self, location_item_id: Uuid, since: DateTime<Utc>) -> Result<Vec<ReservationAdjustment>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM public.reservation_adjustments \
+             WHERE location_item_id = $1 AND corrected_at > $2 ORDER BY corrected_at DESC",
+            ADJUSTMENT_COLUMNS
+        ))
+        .bind(location_item_id)
+        .bind(since)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(Self::map_adjustment).collect()
+    }
+
+    async fn list_adjustments(&self, tenant_id: Uuid, limit: i64) -> Result<Vec<ReservationAdjustment>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM public.reservation_adjustments \
+             WHERE tenant_id = $1 ORDER BY corrected_at DESC LIMIT $2",
+            ADJUSTMENT_COLUMNS
+        ))
+        .bind(tenant_id)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(Self::map_adjustment).collect()
+    }
+}
+
+/// Persistence for pick waves and the stock changes of picking
+#[async_trait]
+pub trait PickWaveRepository: Send + Sync {
+    /// Active reservations at the location matching the selection that are not
+    /// in an open wave, oldest first
+    async fn find_pick_candidates(
+        &self,
+        tenant_id: Uuid,
+        location_id: Uuid,
+        selection: &WaveSelection,
+    ) -> Result<Vec<PickCandidate>>;
+    /// Store a new wave; fails if one of its reservations went into another open wave meanwhile
+    async fn create_wave(&self, wave: &PickWave) -> Result<()>;
+    async fn get_wave(&self, tenant_id: Uuid, wave_id: Uuid) -> Result<Option<PickWave>>;
+    /// Most recently created first
+    async fn list_waves(
+        &self,
+        tenant_id: Uuid,
+        status: Option<WaveStatus>,
+        assigned_to: Option<Uuid>,
+    ) -> Result<Vec<PickWave>>;
+    /// Free stock of the product at the tenant's other locations
+    async fn find_stock_options(
+        &self,
+        tenant_id: Uuid,
+        product_id: Uuid,
+        excluded_location_id: Uuid,
+    ) -> Result<Vec<StockOption>>;
+    /// Store a line confirmation and reserve its re-allocated shortage together;
+    /// fails if the line was confirmed meanwhile
+    async fn confirm_line(&self, wave: &PickWave, line_id: Uuid, reallocation: Option<Reallocation>) -> Result<()>;
+    /// Book the picks of a closing wave and fulfil their reservations together;
+    /// fails if the wave was closed meanwhile
+    async fn close_wave(&self, wave: &PickWave, closing: &WaveClosing) -> Result<()>;
+}
+
+pub struct PostgresPickWaveRepository {
+    pool: Pool<Postgres>,
+}
+
+const PICK_WAVE_COLUMNS: &str = "id, tenant_id, wave_number, location_id, status, path_strategy, cutoff_at, \
+    assigned_to, created_by, created_at, updated_at, closed_by, closed_at";
+
+const PICK_LINE_COLUMNS: &str = "id, sequence, reservation_id, product_id, location_item_id, zone_code, bin_code, \
+    reference_number, requested_quantity, picked_quantity, status, short_reason, short_resolution, \
+    reallocated_location_id, reallocated_reservation_id, confirmed_by, confirmed_at";
+
+impl PostgresPickWaveRepository {
+    pub fn new(pool: Pool<Postgres>) -> Self {
+        Self { pool }
+    }
+
+    fn map_wave(row: &sqlx::postgres::PgRow) -> Result<PickWave> {
+        let status: String = row.try_get("status")?;
+        Ok(PickWave {
+            id: row.try_get("id")?,
+            tenant_id: row.try_get("tenant_id")?,
+            wave_number: row.try_get("wave_number")?,
+            location_id: row.try_get("location_id")?,
+            status: WaveStatus::parse(&status)
+                .ok_or_else(|| MasterDataError::DatabaseError(format!("Unknown wave status: {}", status)))?,
+            path_strategy: row.try_get("path_strategy")?,
+            cutoff_at: row.try_get("cutoff_at")?,
+            assigned_to: row.try_get("assigned_to")?,
+            lines: Vec::new(),
+            created_by: row.try_get("created_by")?,
+            created_at: row.try_get("created_at")?,
+            updated_at: row.try_get("updated_at")?,
+            closed_by: row.try_get("closed_by")?,
+            closed_at: row.try_get("closed_at")?,
+        })
+    }
+
+    fn map_line(row: &sqlx::postgres::PgRow) -> Result<PickLine> {
+        let status: String = row.try_get("status")?;
+        let resolution: Option<String> = row.try_get("short_resolution")?;
+        Ok(PickLine {
+            id: row.try_get("id")?,
+            sequence: row.try_get("sequence")?,
+            reservation_id: row.try_get("reservation_id")?,
+            product_id: row.try_get("product_id")?,
+            location_item_id: row.try_get("location_item_id")?,
+            zone_code: row.try_get("zone_code")?,
+            bin_code: row.try_get("bin_code")?,
+            reference_number: row.try_get("reference_number")?,
+            requested_quantity: row.try_get("requested_quantity")?,
+            picked_quantity: row.try_get("picked_quantity")?,
+            status: PickLineStatus::parse(&status)
+                .ok_or_else(|| MasterDataError::DatabaseError(format!("Unknown pick line status: {}", status)))?,
+            short_reason: row.try_get("short_reason")?,
+            short_resolution: resolution
+                .map(|value| {
+                    ShortPickResolution::parse(&value)
+                        .ok_or_else(|| MasterDataError::DatabaseError(format!("Unknown short pick resolution: {}", value)))
+                })
+                .transpose()?,
+            reallocated_location_id: row.try_get("reallocated_location_id")?,
+            reallocated_reservation_id: row.try_get("reallocated_reservation_id")?,
+            confirmed_by: row.try_get("confirmed_by")?,
+            confirmed_at: row.try_get("confirmed_at")?,
+        })
+    }
+
+    /// Load the lines of the given waves in path order
+    async fn attach_lines(&self, tenant_id: Uuid, waves: &mut [PickWave]) -> Result<()> {
+        if waves.is_empty() {
+            return Ok(());
+        }
+        let ids: Vec<Uuid> = waves.iter().map(|wave| wave.id).collect();
+        let rows = sqlx::query(&format!(
+            "SELECT wave_id, {} FROM public.pick_lines WHERE tenant_id = $1 AND wave_id = ANY($2) \
+             ORDER BY wave_id, sequence",
+            PICK_LINE_COLUMNS
+        ))
+        .bind(tenant_id)
+        .bind(&ids)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut lines: HashMap<Uuid, Vec<PickLine>> = HashMap::new();
+        for row in &rows {
+            lines.entry(row.try_get("wave_id")?).or_default().push(Self::map_line(row)?);
+        }
+        for wave in waves.iter_mut() {
+            wave.lines = lines.remove(&wave.id).unwrap_or_default();
+        }
+        Ok(())
+    }
+
+    async fn update_line(tx: &mut sqlx::Transaction<'_, Postgres>, line: &PickLine, expected: PickLineStatus) -> Result<u64> {
+        let updated = sqlx::query(
+            r#"
+            UPDATE public.pick_lines
+            SET picked_quantity = $3, status = $4, short_reason = $5, short_resolution = $6,
+                reallocated_location_id = $7, reallocated_reservation_id = $8, confirmed_by = $9, confirmed_at = $10
+            WHERE id = $1 AND status = $2
+            "#,
+        )
+        .bind(line.id)
+        .bind(expected.as_str())
+        .bind(line.picked_quantity)
+        .bind(line.status.as_str())
+        .bind(&line.short_reason)
+        .bind(line.short_resolution.map(ShortPickResolution::as_str))
+        .bind(line.reallocated_location_id)
+        .bind(line.reallocated_reservation_id)
+        .bind(line.confirmed_by)
+        .bind(line.confirmed_at)
+        .execute(&mut **tx)
+        .await?;
+        Ok(updated.rows_affected())
+    }
+}
+
+#[async_trait]
+impl PickWaveRepository for PostgresPickWaveRepository {
+    async fn find_pick_candidates(
+        &self,
+        tenant_id: Uuid,
+        location_id: Uuid,
+        selection: &WaveSelection,
+    ) -> Result<Vec<PickCandidate>> {
+        let (reservation_ids, cutoff_at) = match selection {
+            WaveSelection::Manual { reservation_ids } => (Some(reservation_ids.clone()), None),
+            WaveSelection::Cutoff { cutoff_at } => (None, Some(*cutoff_at)),
+        };
+        let rows = sqlx::query(
+            r#"
+            SELECT r.id AS reservation_id, li.product_id, li.id AS location_item_id, li.zone_code, li.bin_code,
+                   r.reference_number, r.reserved_quantity, r.reserved_at
+            FROM stock_reservations r
+            JOIN location_items li ON li.id = r.location_item_id
+            JOIN locations l ON l.id = li.location_id
+            WHERE l.tenant_id = $1 AND li.location_id = $2 AND r.status = 'active'
+              AND ($3::uuid[] IS NULL OR r.id = ANY($3))
+              AND ($4::timestamptz IS NULL OR r.reserved_at <= $4)
+              AND NOT EXISTS (
+                  SELECT 1 FROM public.pick_lines pl
+                  JOIN public.pick_waves pw ON pw.id = pl.wave_id
+                  WHERE pl.reservation_id = r.id AND pw.status = 'open'
+              )
+            ORDER BY r.reserved_at, r.id
+            "#,
+        )
+        .bind(tenant_id)
+        .bind(location_id)
+        .bind(reservation_ids)
+        .bind(cutoff_at)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                Ok(PickCandidate {
+                    reservation_id: row.try_get("reservation_id")?,
+                    product_id: row.try_get("product_id")?,
+                    location_item_id: row.try_get("location_item_id")?,
+                    zone_code: row.try_get("zone_code")?,
+                    bin_code: row.try_get("bin_code")?,
+                    reference_number: row.try_get("reference_number")?,
+                    quantity: row.try_get("reserved_quantity")?,
+                    reserved_at: row.try_get("reserved_at")?,
+                })
+            })
+            .collect()
+    }
+
+    async fn create_wave(&self, wave: &PickWave) -> Result<()> {
+        ensure_actor(wave.created_by)?;
+        let reservation_ids: Vec<Uuid> = wave.lines.iter().map(|line| line.reservation_id).collect();
+        let mut tx = self.pool.begin().await?;
+
+        // Lock the reservations first; the check below then sees waves created
+        // by a concurrent call that held them before us
+        let locked: Vec<Uuid> =
+            sqlx::query_scalar("SELECT id FROM stock_reservations WHERE id = ANY($1) AND status = 'active' FOR UPDATE")
+                .bind(&reservation_ids)
+                .fetch_all(&mut *tx)
+                .await?;
+        let taken: Option<Uuid> = sqlx::query_scalar(
+            r#"
+            SELECT pl.reservation_id FROM public.pick_lines pl
+            JOIN public.pick_waves pw ON pw.id = pl.wave_id
+            WHERE pl.reservation_id = ANY($1) AND pw.status = 'open'
+            LIMIT 1
+            "#,
+        )
+        .bind(&reservation_ids)
+        .fetch_optional(&mut *tx)
+        .await?;
+        if locked.len() != reservation_ids.len() || taken.is_some() {
+            return Err(MasterDataError::ValidationError {
+                field: "selection".to_string(),
+                message: "A selected reservation was fulfilled or put into another wave meanwhile".to_string(),
+            });
+        }
+
+        sqlx::query(&format!(
+            "INSERT INTO public.pick_waves ({}) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)",
+            PICK_WAVE_COLUMNS
+        ))
+        .bind(wave.id)
+        .bind(wave.tenant_id)
+        .bind(&wave.wave_number)
+        .bind(wave.location_id)
+        .bind(wave.status.as_str())
+        .bind(&wave.path_strategy)
+        .bind(wave.cutoff_at)
+        .bind(wave.assigned_to)
+        .bind(wave.created_by)
+        .bind(wave.created_at)
+        .bind(wave.updated_at)
+        .bind(wave.closed_by)
+        .bind(wave.closed_at)
+        .execute(&mut *tx)
+        .await?;
+
+        for line in &wave.lines {
+            sqlx::query(&format!(
+                "INSERT INTO public.pick_lines (tenant_id, wave_id, {}) \
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19)",
+                PICK_LINE_COLUMNS
+            ))
+            .bind(wave.tenant_id)
+            .bind(wave.id)
+            .bind(line.id)
+            .bind(line.sequence)
+            .bind(line.reservation_id)
+            .bind(line.product_id)
+            .bind(line.location_item_id)
+            .bind(&line.zone_code)
+            .bind(&line.bin_code)
+            .bind(&line.reference_number)
+            .bind(line.requested_quantity)
+            .bind(line.picked_quantity)
+            .bind(line.status.as_str())
+            .bind(&line.short_reason)
+            .bind(line.short_resolution.map(ShortPickResolution::as_str))
+            .bind(line.reallocated_location_id)
+            .bind(line.reallocated_reservation_id)
+            .bind(line.confirmed_by)
+            .bind(line.confirmed_at)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn get_wave(&self, tenant_id: Uuid, wave_id: Uuid) -> Result<Option<PickWave>> {
+        let row = sqlx::query(&format!(
+            "SELECT {} FROM public.pick_waves WHERE tenant_id = $1 AND id = $2",
+            PICK_WAVE_COLUMNS
+        ))
+        .bind(tenant_id)
+        .bind(wave_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        let mut waves = vec![Self::map_wave(&row)?];
+        self.attach_lines(tenant_id, &mut waves).await?;
+        Ok(waves.pop())
+    }
+
+    async fn list_waves(
+        &self,
+        tenant_id: Uuid,
+        status: Option<WaveStatus>,
+        assigned_to: Option<Uuid>,
+    ) -> Result<Vec<PickWave>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM public.pick_waves \
+             WHERE tenant_id = $1 AND ($2::text IS NULL OR status = $2) AND ($3::uuid IS NULL OR assigned_to = $3) \
+             ORDER BY created_at DESC",
+            PICK_WAVE_COLUMNS
+        ))
+        .bind(tenant_id)
+        .bind(status.map(WaveStatus::as_str))
+        .bind(assigned_to)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut waves = rows.iter().map(Self::map_wave).collect::<Result<Vec<_>>>()?;
+        self.attach_lines(tenant_id, &mut waves).await?;
+        Ok(waves)
+    }
+
+    async fn find_stock_options(
+        &self,
+        tenant_id: Uuid,
+        product_id: Uuid,
+        excluded_location_id: Uuid,
+    ) -> Result<Vec<StockOption>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT li.location_id, li.id AS location_item_id, li.quantity_available - li.quantity_reserved AS free_quantity
+            FROM location_items li
+            JOIN locations l ON l.id = li.location_id
+            WHERE l.tenant_id = $1 AND l.is_active AND li.product_id = $2 AND li.location_id <> $3
+              AND li.quantity_available > li.quantity_reserved
+            "#,
+        )
+        .bind(tenant_id)
+        .bind(product_id)
+        .bind(excluded_location_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                Ok(StockOption {
+                    location_id: row.try_get("location_id")?,
+                    location_item_id: row.try_get("location_item_id")?,
+                    free_quantity: row.try_get("free_quantity")?,
+                })
+            })
+            .collect()
+    }
+
+    async fn confirm_line(&self, wave: &PickWave, line_id: Uuid, reallocation: Option<Reallocation>) -> Result<()> {
+        let line = wave
+            .lines
+            .iter()
+            .find(|line| line.id == line_id)
+            .ok_or_else(|| MasterDataError::NotFoundError(format!("Pick line {}", line_id)))?;
+        let mut tx = self.pool.begin().await?;
+
+        let open: Option<String> = sqlx::query_scalar(
+            "SELECT status FROM public.pick_waves WHERE tenant_id = $1 AND id = $2 AND status = 'open' FOR SHARE",
+        )
+        .bind(wave.tenant_id)
+        .bind(wave.id)
+        .fetch_optional(&mut *tx)
+        .await?;
+        if open.is_none() || Self::update_line(&mut tx, line, PickLineStatus::Open).await? == 0 {
+            return Err(MasterDataError::ValidationError {
+                field: "line_id".to_string(),
+                message: format!("Line {} was confirmed or its wave closed meanwhile", line.sequence),
+            });
+        }
+
+        if let Some(reallocation) = reallocation {
+            let reserved = sqlx::query(
+                r#"
+                UPDATE location_items
+                SET quantity_reserved = quantity_reserved + $2, updated_at = NOW()
+                WHERE id = $1 AND quantity_available - quantity_reserved >= $2
+                "#,
+            )
+            .bind(reallocation.location_item_id)
+            .bind(reallocation.quantity)
+            .execute(&mut *tx)
+            .await?;
+            if reserved.rows_affected() == 0 {
+                return Err(MasterDataError::ValidationError {
+                    field: "picked_quantity".to_string(),
+                    message: "Stock chosen for the shortage is no longer free; confirm the line again".to_string(),
+                });
+            }
+
+            sqlx::query(
+                r#"
+                INSERT INTO stock_reservations (
+                    id, location_item_id, reserved_quantity, reservation_type, reference_id, reference_number,
+                    expires_at, created_by
+                )
+                SELECT $1, $2, $3, r.reservation_type, r.reference_id, r.reference_number, r.expires_at, $5
+                FROM stock_reservations r
+                WHERE r.id = $4
+                "#,
+            )
+            .bind(reallocation.reservation_id)
+            .bind(reallocation.location_item_id)
+            .bind(reallocation.quantity)
+            .bind(reallocation.original_reservation_id)
+            .bind(line.confirmed_by)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        sqlx::query("UPDATE public.pick_waves SET updated_at = $2 WHERE id = $1")
+            .bind(wave.id)
+            .bind(wave.updated_at)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn close_wave(&self, wave: &PickWave, closing: &WaveClosing) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        let closed = sqlx::query(
+            r#"
+            UPDATE public.pick_waves
+            SET status = $3, closed_by = $4, closed_at = $5, updated_at = $6
+            WHERE tenant_id = $1 AND id = $2 AND status = 'open'
+            "#,
+        )
+        .bind(wave.tenant_id)
+        .bind(wave.id)
+        .bind(wave.status.as_str())
+        .bind(wave.closed_by)
+        .bind(wave.closed_at)
+        .bind(wave.updated_at)
+        .execute(&mut *tx)
+        .await?;
+        if closed.rows_affected() == 0 {
+            return Err(MasterDataError::InvalidWaveTransition {
+                wave_number: wave.wave_number.clone(),
+                from: WaveStatus::Closed.as_str().to_string(),
+                to: WaveStatus::Closed.as_str().to_string(),
+            });
+        }
+
+        sqlx::query("UPDATE public.pick_lines SET status = $2 WHERE wave_id = $1 AND status = $3")
+            .bind(wave.id)
+            .bind(PickLineStatus::Released.as_str())
+            .bind(PickLineStatus::Open.as_str())
+            .execute(&mut *tx)
+            .await?;
+
+        for movement in &closing.movements {
+            sqlx::query(
+                r#"
+                INSERT INTO inventory_transactions (
+                    id, transaction_number, transaction_type, product_id, location_id, quantity_change,
+                    reference_document, reference_number, reason_code, created_by, created_at, transaction_date
+                )
+                VALUES ($1, CONCAT('TXN-', EXTRACT(EPOCH FROM NOW())), $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+                "#,
+            )
+            .bind(movement.id)
+            .bind(&movement.movement_type)
+            .bind(movement.product_id)
+            .bind(movement.location_id)
+            .bind(movement.quantity)
+            .bind(&movement.reference_document)
+            .bind(&movement.reference_number)
+            .bind(&movement.reason)
+            .bind(movement.operator_id)
+            .bind(movement.created_at)
+            .bind(movement.effective_date)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        // Picked units leave the shelf; the whole hold ends, the shortage being
+        // re-allocated or backordered at confirmation
+        for fulfilled in &closing.fulfilled {
+            let updated = sqlx::query(
+                r#"
+                UPDATE location_items
+                SET quantity_available = quantity_available - $2,
+                    quantity_reserved = GREATEST(quantity_reserved - $3, 0),
+                    updated_at = NOW()
+                WHERE id = $1 AND quantity_available >= $2
+                "#,
+            )
+            .bind(fulfilled.location_item_id)
+            .bind(fulfilled.picked_quantity)
+            .bind(fulfilled.released_quantity)
+            .execute(&mut *tx)
+            .await?;
+            if updated.rows_affected() == 0 {
+                return Err(MasterDataError::ValidationError {
+                    field: "wave_id".to_string(),
+                    message: format!("Not enough stock on hand to book the pick of reservation {}", fulfilled.reservation_id),
+                });
+            }
+        }
+
+        let reservation_ids: Vec<Uuid> = closing.fulfilled.iter().map(|f| f.reservation_id).collect();
+        sqlx::query(
+            "UPDATE stock_reservations SET status = 'fulfilled', released_at = $2 WHERE id = ANY($1) AND status = 'active'",
+        )
+        .bind(&reservation_ids)
+        .bind(wave.closed_at)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+pub trait LocationCapacityRepository: Send + Sync {
+    /// Capacities of the location and its bins; empty if the location is not the tenant's
+    async fn get_capacities(&self, tenant_id: Uuid, location_id: Uuid) -> Result<Vec<LocationCapacity>>;
+    /// Replace the capacities of a location; fails if the location is not the tenant's
+    async fn save_capacities(
+        &self,
+        tenant_id: Uuid,
+        location_id: Uuid,
+        capacities: &[LocationCapacity],
+        updated_by: Uuid,
+    ) -> Result<()>;
+    /// Units at the location per product, and units of open transfers on their way there
+    async fn get_stored_quantities(&self, tenant_id: Uuid, location_id: Uuid) -> Result<Vec<StoredQuantity>>;
+    /// Dimensions and space overrides of the tenant's products among `product_ids`
+    async fn get_product_space(&self, tenant_id: Uuid, product_ids: &[Uuid]) -> Result<HashMap<Uuid, ProductSpace>>;
+    /// Replace the space overrides of a product; fails if the product is not the tenant's
+    async fn save_space_overrides(
+        &self,
+        tenant_id: Uuid,
+        product_id: Uuid,
+        overrides: &HashMap<CapacityUnit, f64>,
+        updated_by: Uuid,
+    ) -> Result<()>;
+}
+
+pub struct PostgresLocationCapacityRepository {
+    pool: Pool<Postgres>,
+}
+
+impl PostgresLocationCapacityRepository {
+    pub fn new(pool: Pool<Postgres>) -> Self {
+        Self { pool }
+    }
+
+    /// Tenant owning a location, for callers that only know the location
+    pub async fn location_tenant(&self, location_id: Uuid) -> Result<Option<Uuid>> {
+        let tenant_id: Option<Option<Uuid>> = sqlx::query_scalar("SELECT tenant_id FROM locations WHERE id = $1")
+            .bind(location_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(tenant_id.flatten())
+    }
+}
+
+#[async_trait]
+impl LocationCapacityRepository for PostgresLocationCapacityRepository {
+    async fn get_capacities(&self, tenant_id: Uuid, location_id: Uuid) -> Result<Vec<LocationCapacity>> {
+        let rows = sqlx::query(
+            "SELECT location_id, bin_code, unit, capacity FROM public.location_capacities \
+             WHERE tenant_id = $1 AND location_id = $2 ORDER BY bin_code NULLS FIRST",
+        )
+        .bind(tenant_id)
+        .bind(location_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                let unit: String = row.get("unit");
+                Ok(LocationCapacity {
+                    location_id: row.get("location_id"),
+                    bin_code: row.get("bin_code"),
+                    unit: CapacityUnit::parse(&unit)
+                        .ok_or_else(|| MasterDataError::DatabaseError(format!("Unknown capacity unit: {}", unit)))?,
+                    capacity: row.get("capacity"),
+                })
+            })
+            .collect()
+    }
+
+    async fn save_capacities(
+        &self,
+        tenant_id: Uuid,
+        location_id: Uuid,
+        capacities: &[LocationCapacity],
+        updated_by: Uuid,
+    ) -> Result<()> {
+        ensure_actor(updated_by)?;
+        let mut tx = self.pool.begin().await?;
+
+        let owned: Option<Uuid> = sqlx::query_scalar("SELECT id FROM locations WHERE id = $1 AND tenant_id = $2 FOR UPDATE")
+            .bind(location_id)
+            .bind(tenant_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+        if owned.is_none() {
+            return Err(MasterDataError::NotFoundError(format!("Location {}", location_id)));
+        }
+
+        sqlx::query("DELETE FROM public.location_capacities WHERE tenant_id = $1 AND location_id = $2")
+            .bind(tenant_id)
+            .bind(location_id)
+            .execute(&mut *tx)
+            .await?;
+        for capacity in capacities {
+            sqlx::query(
+                "INSERT INTO public.location_capacities (tenant_id, location_id, bin_code, unit, capacity, updated_by) \
+                 VALUES ($1, $2, $3, $4, $5, $6)",
+            )
+            .bind(tenant_id)
+            .bind(location_id)
+            .bind(&capacity.bin_code)
+            .bind(capacity.unit.as_str())
+            .bind(capacity.capacity)
+            .bind(updated_by)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn get_stored_quantities(&self, tenant_id: Uuid, location_id: Uuid) -> Result<Vec<StoredQuantity>> {
+        let statuses: Vec<String> = INBOUND_TRANSFER_STATUSES.iter().map(|s| s.to_string()).collect();
+        let rows = sqlx::query(
+            r#"
+            SELECT li.product_id, li.bin_code, li.quantity_available::BIGINT AS quantity
+            FROM location_items li
+            JOIN locations l ON l.id = li.location_id
+            WHERE l.tenant_id = $1 AND li.location_id = $2 AND li.quantity_available > 0
+            UNION ALL
+            SELECT st.product_id, NULL,
+                   (COALESCE(st.quantity_shipped, st.quantity) - COALESCE(st.quantity_received, 0))::BIGINT
+            FROM stock_transfers st
+            JOIN locations l ON l.id = st.to_location_id
+            WHERE l.tenant_id = $1 AND st.to_location_id = $2 AND st.status::text = ANY($3)
+              AND COALESCE(st.quantity_shipped, st.quantity) > COALESCE(st.quantity_received, 0)
+            "#,
+        )
+        .bind(tenant_id)
+        .bind(location_id)
+        .bind(&statuses)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| StoredQuantity {
+                product_id: row.get("product_id"),
+                bin_code: row.get("bin_code"),
+                quantity: row.get("quantity"),
+            })
+            .collect())
+    }
+
+    async fn get_product_space(&self, tenant_id: Uuid, product_ids: &[Uuid]) -> Result<HashMap<Uuid, ProductSpace>> {
+        if product_ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let rows = sqlx::query(
+            "SELECT id, dimensions_length::float8 AS length_cm, dimensions_width::float8 AS width_cm, \
+             dimensions_height::float8 AS height_cm FROM products WHERE tenant_id = $1 AND id = ANY($2)",
+        )
+        .bind(tenant_id)
+        .bind(product_ids)
+        .fetch_all(&self.pool)
+        .await?;
+        let mut products: HashMap<Uuid, ProductSpace> = rows
+            .iter()
+            .map(|row| {
+                let product_id: Uuid = row.get("id");
+                let space = ProductSpace {
+                    product_id,
+                    length_cm: row.get("length_cm"),
+                    width_cm: row.get("width_cm"),
+                    height_cm: row.get("height_cm"),
+                    overrides: HashMap::new(),
+                };
+                (product_id, space)
+            })
+            .collect();
+
+        let overrides = sqlx::query(
+            "SELECT product_id, unit, space_per_unit FROM public.product_space_factors \
+             WHERE tenant_id = $1 AND product_id = ANY($2)",
+        )
+        .bind(tenant_id)
+        .bind(product_ids)
+        .fetch_all(&self.pool)
+        .await?;
+        for row in &overrides {
+            let unit: String = row.get("unit");
+            let unit = CapacityUnit::parse(&unit)
+                .ok_or_else(|| MasterDataError::DatabaseError(format!("Unknown capacity unit: {}", unit)))?;
+            if let Some(product) = products.get_mut(&row.get::<Uuid, _>("product_id")) {
+                product.overrides.insert(unit, row.get("space_per_unit"));
+            }
+        }
+        Ok(products)
+    }
+
+    async fn save_space_overrides(
+        &self,
+        tenant_id: Uuid,
+        product_id: Uuid,
+        overrides: &HashMap<CapacityUnit, f64>,
+        updated_by: Uuid,
+    ) -> Result<()> {
+        ensure_actor(updated_by)?;
+        let mut tx = self.pool.begin().await?;
+
+        let owned: Option<Uuid> = sqlx::query_scalar("SELECT id FROM products WHERE id = $1 AND tenant_id = $2")
+            .bind(product_id)
+            .bind(tenant_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+        if owned.is_none() {
+            return Err(MasterDataError::ProductNotFound { id: product_id.to_string() });
+        }
+
+        sqlx::query("DELETE FROM public.product_space_factors WHERE tenant_id = $1 AND product_id = $2")
+            .bind(tenant_id)
+            .bind(product_id)
+            .execute(&mut *tx)
+            .await?;
+        for (unit, space_per_unit) in overrides {
+            sqlx::query(
+                "INSERT INTO public.product_space_factors (tenant_id, product_id, unit, space_per_unit, updated_by) \
+                 VALUES ($1, $2, $3, $4, $5)",
+            )
+            .bind(tenant_id)
+            .bind(product_id)
+            .bind(unit.as_str())
+            .bind(space_per_unit)
+            .bind(updated_by)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+pub trait PurchaseReceiptRepository: Send + Sync {
+    /// The purchase order with its lines, if it delivers to one of the tenant's locations
+    async fn get_receivable_order(&self, tenant_id: Uuid, purchase_order_id: Uuid) -> Result<Option<ReceivableOrder>>;
+    /// Book received quantities and statuses on the order lines, set the order
+    /// status, put the stock into the location, its batches and serial units
+    /// and record the receipt events, all or nothing; fails if a line was
+    /// received or closed in the meantime or a batch is held back
+    async fn record_receipt(&self, tenant_id: Uuid, order: &ReceivableOrder, booking: &ReceiptBooking) -> Result<()>;
+    /// Receipt events of the order, oldest first
+    async fn get_receipt_events(&self, tenant_id: Uuid, purchase_order_id: Uuid) -> Result<Vec<ReceiptEvent>>;
+}
+
+pub struct PostgresPurchaseReceiptRepository {
+    pool: Pool<Postgres>,
+}
+
+impl PostgresPurchaseReceiptRepository {
+    pub fn new(pool: Pool<Postgres>) -> Self {
+        Self { pool }
+    }
+
+    fn map_event(row: &sqlx::postgres::PgRow) -> Result<ReceiptEvent> {
+        let kind: String = row.try_get("event_type")?;
+        let line_status: String = row.try_get("line_status")?;
+        let serial_numbers: Option<Vec<String>> = row.try_get("serial_numbers")?;
+        Ok(ReceiptEvent {
+            id: row.try_get("id")?,
+            receipt_id: row.try_get("receipt_id")?,
+            purchase_order_id: row.try_get("purchase_order_id")?,
+            line_id: row.try_get("line_id")?,
+            product_id: row.try_get("product_id")?,
+            kind: ReceiptEventKind::parse(&kind)
+                .ok_or_else(|| MasterDataError::DatabaseError(format!("Unknown receipt event: {}", kind)))?,
+            quantity: row.try_get("quantity")?,
+            bin_code: row.try_get("bin_code")?,
+            batch_number: row.try_get("batch_number")?,
+            serial_numbers: serial_numbers.unwrap_or_default(),
+            movement_id: row.try_get("movement_id")?,
+            reason: row.try_get("reason")?,
+            line_status: parse_line_status(&line_status)?,
+            received_by: row.try_get("received_by")?,
+            received_at: row.try_get("received_at")?,
+        })
+    }
+}
+
+fn parse_line_status(value: &str) -> Result<LineStatus> {
+    LineStatus::parse(value).ok_or_else(|| MasterDataError::DatabaseError(format!("Unknown purchase order line status: {}", value)))
+}
+
+#[async_trait]
+impl PurchaseReceiptRepository for PostgresPurchaseReceiptRepository {
+    async fn get_receivable_order(&self, tenant_id: Uuid, purchase_order_id: Uuid) -> Result<Option<ReceivableOrder>> {
+        let Some(row) = sqlx::query(
+            "SELECT po.id, po.order_number, po.location_id, po.status::text AS status \
+             FROM purchase_orders po JOIN locations l ON l.id = po.location_id \
+             WHERE l.tenant_id = $1 AND po.id = $2",
+        )
+        .bind(tenant_id)
+        .bind(purchase_order_id)
+        .fetch_optional(&self.pool)
+        .await?
+        else {
+            return Ok(None);
+        };
+
+        let lines = sqlx::query(
+            "SELECT pol.id, pol.product_id, pol.quantity_ordered::int4 AS quantity_ordered, \
+                    pol.quantity_received::int4 AS quantity_received, pol.status, \
+                    COALESCE(p.is_serialized, FALSE) AS serialized, \
+                    EXISTS (SELECT 1 FROM public.product_attributes pa \
+                            WHERE pa.product_id = pol.product_id AND pa.tenant_id = $2 \
+                              AND pa.batch_tracking_required) AS batch_required \
+             FROM purchase_order_lines pol LEFT JOIN products p ON p.id = pol.product_id \
+             WHERE pol.purchase_order_id = $1 ORDER BY pol.id",
+        )
+        .bind(purchase_order_id)
+        .bind(tenant_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(Some(ReceivableOrder {
+            id: row.get("id"),
+            order_number: row.get("order_number"),
+            location_id: row.get("location_id"),
+            status: row.get("status"),
+            lines: lines
+                .iter()
+                .map(|line| {
+                    let status: String = line.try_get("status")?;
+                    Ok(ReceivableLine {
+                        line_id: line.try_get("id")?,
+                        product_id: line.try_get("product_id")?,
+                        quantity_ordered: line.try_get("quantity_ordered")?,
+                        quantity_received: line.try_get("quantity_received")?,
+                        status: parse_line_status(&status)?,
+                        batch_required: line.try_get("batch_required")?,
+                        serialized: line.try_get("serialized")?,
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?,
+        }))
+    }
+
+    async fn record_receipt(&self, tenant_id: Uuid, order: &ReceivableOrder, booking: &ReceiptBooking) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        for update in &booking.updates {
+            let updated = sqlx::query(
+                "UPDATE purchase_order_lines SET quantity_received = $5, status = $6, \
+                     closed_short_reason = COALESCE($7, closed_short_reason) \
+                 WHERE id = $1 AND purchase_order_id = $2 AND quantity_received = $3 AND status = $4",
+            )
+            .bind(update.line_id)
+            .bind(order.id)
+            .bind(update.quantity_received_before)
+            .bind(update.status_before.as_str())
+            .bind(update.quantity_received)
+            .bind(update.status.as_str())
+            .bind(&update.close_short_reason)
+            .execute(&mut *tx)
+            .await?;
+            if updated.rows_affected() == 0 {
+                return Err(MasterDataError::ValidationError {
+                    field: "quantity".to_string(),
+                    message: format!("Purchase order {} was received in the meantime", order.order_number),
+                });
+            }
+        }
+
+        sqlx::query("UPDATE purchase_orders SET status = $2, updated_at = NOW() WHERE id = $1")
+            .bind(order.id)
+            .bind(booking.status)
+            .execute(&mut *tx)
+            .await?;
+
+        for movement in &booking.movements {
+            apply_stock_movement(&mut tx, movement).await?;
+        }
+        // Units put away in a bin place a product that had none there
+        for line in booking.lines.iter().filter(|line| line.quantity > 0 && line.bin_code.is_some()) {
+            sqlx::query(
+                "UPDATE location_items SET bin_code = $3 WHERE product_id = $1 AND location_id = $2 AND bin_code IS NULL",
+            )
+            .bind(line.product_id)
+            .bind(order.location_id)
+            .bind(&line.bin_code)
+            .execute(&mut *tx)
+            .await?;
+        }
+        for line in booking.lines.iter().filter(|line| line.quantity > 0) {
+            let batch_number = receiving::batch_number(order, line);
+            // A batch held back after a failed inspection takes no new units
+            let booked = sqlx::query(
+                "INSERT INTO public.product_batches \
+                     (tenant_id, product_id, location_id, batch_number, quantity, purchase_order_id) \
+                 SELECT l.tenant_id, $2, l.id, $3, $4, $5 FROM locations l WHERE l.id = $1 \
+                 ON CONFLICT (tenant_id, product_id, location_id, batch_number) \
+                 DO UPDATE SET quantity = product_batches.quantity + EXCLUDED.quantity, updated_at = NOW() \
+                 WHERE product_batches.quality_status NOT IN ('failed', 'recalled')",
+            )
+            .bind(order.location_id)
+            .bind(line.product_id)
+            .bind(&batch_number)
+            .bind(line.quantity)
+            .bind(order.id)
+            .execute(&mut *tx)
+            .await?;
+            if booked.rows_affected() == 0 {
+                return Err(MasterDataError::ValidationError {
+                    field: "batch_number".to_string(),
+                    message: format!("Batch {} is held back after a failed inspection", batch_number),
+                });
+            }
+        }
+
+        for unit in &booking.serial_units {
+            sqlx::query(&format!(
+                "INSERT INTO serial_units ({}) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)",
+                SERIAL_UNIT_COLUMNS
+            ))
+            .bind(unit.id)
+            .bind(unit.tenant_id)
+            .bind(unit.product_id)
+            .bind(&unit.serial_number)
+            .bind(unit.location_id)
+            .bind(&unit.bin_location)
+            .bind(unit.status.as_str())
+            .bind(unit.receipt_movement_id)
+            .bind(unit.received_at)
+            .bind(unit.updated_at)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| match &e {
+                sqlx::Error::Database(db) if db.is_unique_violation() => {
+                    MasterDataError::DuplicateSerialNumber { serial: unit.serial_number.clone() }
+                }
+                _ => MasterDataError::Database(e),
+            })?;
+        }
+        for event in &booking.serial_events {
+            sqlx::query(
+                r#"
+                INSERT INTO serial_unit_events (
+                    id, tenant_id, serial_unit_id, serial_number, product_id, from_status, to_status,
+                    from_location_id, to_location_id, movement_id, reference, performed_by, occurred_at
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+                "#,
+            )
+            .bind(event.id)
+            .bind(event.tenant_id)
+            .bind(event.serial_unit_id)
+            .bind(&event.serial_number)
+            .bind(event.product_id)
+            .bind(event.from_status.map(SerialStatus::as_str))
+            .bind(event.to_status.as_str())
+            .bind(event.from_location_id)
+            .bind(event.to_location_id)
+            .bind(event.movement_id)
+            .bind(&event.reference)
+            .bind(event.performed_by)
+            .bind(event.occurred_at)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        for event in &booking.events {
+            sqlx::query(
+                "INSERT INTO public.purchase_order_receipt_events \
+                     (id, tenant_id, receipt_id, purchase_order_id, line_id, product_id, event_type, quantity, \
+                      bin_code, batch_number, serial_numbers, movement_id, reason, line_status, received_by, received_at) \
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16)",
+            )
+            .bind(event.id)
+            .bind(tenant_id)
+            .bind(event.receipt_id)
+            .bind(event.purchase_order_id)
+            .bind(event.line_id)
+            .bind(event.product_id)
+            .bind(event.kind.as_str())
+            .bind(event.quantity)
+            .bind(&event.bin_code)
+            .bind(&event.batch_number)
+            .bind(&event.serial_numbers)
+            .bind(event.movement_id)
+            .bind(&event.reason)
+            .bind(event.line_status.as_str())
+            .bind(event.received_by)
+            .bind(event.received_at)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn get_receipt_events(&self, tenant_id: Uuid, purchase_order_id: Uuid) -> Result<Vec<ReceiptEvent>> {
+        let rows = sqlx::query(
+            "SELECT id, receipt_id, purchase_order_id, line_id, product_id, event_type, quantity, bin_code, \
+                    batch_number, serial_numbers, movement_id, reason, line_status, received_by, received_at \
+             FROM public.purchase_order_receipt_events \
+             WHERE tenant_id = $1 AND purchase_order_id = $2 \
+             ORDER BY received_at, event_type DESC, id",
+        )
+        .bind(tenant_id)
+        .bind(purchase_order_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(Self::map_event).collect()
+    }
+}
+
+/// Which stock the valuation reports cover
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StockValueFilter {
+    pub location_id: Option<Uuid>,
+    pub category_id: Option<Uuid>,
+}
+
+#[async_trait]
+pub trait InventoryValuationRepository: Send + Sync {
+    /// Every product on hand at a location of the tenant, with its prices
+    async fn get_stock_value_lines(&self, tenant_id: Uuid, filter: &StockValueFilter) -> Result<Vec<StockValueLine>>;
+}
+
+pub struct PostgresInventoryValuationRepository {
+    pool: Pool<Postgres>,
+}
+
+impl PostgresInventoryValuationRepository {
+    pub fn new(pool: Pool<Postgres>) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl InventoryValuationRepository for PostgresInventoryValuationRepository {
+    async fn get_stock_value_lines(&self, tenant_id: Uuid, filter: &StockValueFilter) -> Result<Vec<StockValueLine>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT p.id AS product_id, p.sku, p.name, p.category_id, li.location_id, l.name AS location_name,
+                   li.quantity_available::BIGINT AS quantity,
+                   COALESCE((SELECT SUM(cs.quantity) FROM consignment_stock cs
+                             WHERE cs.tenant_id = $1 AND cs.product_id = li.product_id
+                               AND cs.location_id = li.location_id), 0)::BIGINT AS consignment_quantity,
+                   p.cost_price, p.base_price, p.currency::TEXT AS currency
+            FROM location_items li
+            JOIN products p ON p.id = li.product_id
+            JOIN locations l ON l.id = li.location_id
+            WHERE p.tenant_id = $1 AND l.tenant_id = $1 AND li.quantity_available > 0
+              AND ($2::UUID IS NULL OR li.location_id = $2)
+              AND ($3::UUID IS NULL OR p.category_id = $3)
+            ORDER BY p.sku, l.name
+            "#,
+        )
+        .bind(tenant_id)
+        .bind(filter.location_id)
+        .bind(filter.category_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                let currency: String = row.try_get("currency")?;
+                Ok(StockValueLine {
+                    product_id: row.try_get("product_id")?,
+                    sku: row.try_get("sku")?,
+                    name: row.try_get("name")?,
+                    category_id: row.try_get("category_id")?,
+                    location_id: row.try_get("location_id")?,
+                    location_name: row.try_get("location_name")?,
+                    quantity: row.try_get("quantity")?,
+                    consignment_quantity: row.try_get("consignment_quantity")?,
+                    unit_cost: row.try_get("cost_price")?,
+                    unit_price: row.try_get("base_price")?,
+                    currency: currency.trim().to_uppercase(),
+                })
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+pub trait ConsignmentRepository: Send + Sync {
+    /// Fails with the matching not-found error unless the supplier, the
+    /// product and each location belong to the tenant
+    async fn check_references(&self, tenant_id: Uuid, supplier_id: Uuid, product_id: Uuid, location_ids: &[Uuid]) -> Result<()>;
+    /// Units of the supplier's stock of the product at the location
+    async fn get_balance(&self, tenant_id: Uuid, supplier_id: Uuid, product_id: Uuid, location_id: Uuid) -> Result<i32>;
+    /// The supplier's catalog entries of the product
+    async fn get_catalog_entries(&self, tenant_id: Uuid, supplier_id: Uuid, product_id: Uuid) -> Result<Vec<SupplierCatalogEntry>>;
+    /// Book the movements on the suppliers' stock and apply the stock
+    /// movements to the locations, all or nothing; fails if the supplier's
+    /// stock changed in the meantime below what a movement takes
+    async fn record(&self, tenant_id: Uuid, movements: &[ConsignmentMovement], stock: &[InventoryMovement]) -> Result<()>;
+    /// Stock on hand per supplier, product and location, optionally of one supplier
+    async fn list_balances(&self, tenant_id: Uuid, supplier_id: Option<Uuid>) -> Result<Vec<ConsignmentBalance>>;
+    /// The supplier's movements created from `from` up to and including `to`
+    async fn list_movements(&self, tenant_id: Uuid, supplier_id: Uuid, from: NaiveDate, to: NaiveDate) -> Result<Vec<ConsignmentMovement>>;
+}
+
+pub struct PostgresConsignmentRepository {
+    pool: Pool<Postgres>,
+}
+
+impl PostgresConsignmentRepository {
+    pub fn new(pool: Pool<Postgres>) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl ConsignmentRepository for PostgresConsignmentRepository {
+    async fn check_references(&self, tenant_id: Uuid, supplier_id: Uuid, product_id: Uuid, location_ids: &[Uuid]) -> Result<()> {
+        let row = sqlx::query(
+            "SELECT EXISTS (SELECT 1 FROM suppliers WHERE id = $2 AND tenant_id = $1) AS supplier, \
+                    EXISTS (SELECT 1 FROM products WHERE id = $3 AND tenant_id = $1) AS product, \
+                    ARRAY(SELECT wanted FROM UNNEST($4::uuid[]) AS wanted \
+                          WHERE NOT EXISTS (SELECT 1 FROM locations WHERE id = wanted AND tenant_id = $1)) AS missing_locations",
+        )
+        .bind(tenant_id)
+        .bind(supplier_id)
+        .bind(product_id)
+        .bind(location_ids)
+        .fetch_one(&self.pool)
+        .await?;
+
+        if !row.try_get::<bool, _>("supplier")? {
+            return Err(MasterDataError::SupplierNotFound { id: supplier_id.to_string() });
+        }
+        if !row.try_get::<bool, _>("product")? {
+            return Err(MasterDataError::ProductNotFound { id: product_id.to_string() });
+        }
+        let missing: Vec<Uuid> = row.try_get("missing_locations")?;
+        if let Some(location_id) = missing.first() {
+            return Err(MasterDataError::LocationNotFound { id: location_id.to_string() });
+        }
+        Ok(())
+    }
+
+    async fn get_balance(&self, tenant_id: Uuid, supplier_id: Uuid, product_id: Uuid, location_id: Uuid) -> Result<i32> {
+        let quantity: Option<i32> = sqlx::query_scalar(
+            "SELECT quantity FROM consignment_stock \
+             WHERE tenant_id = $1 AND supplier_id = $2 AND product_id = $3 AND location_id = $4",
+        )
+        .bind(tenant_id)
+        .bind(supplier_id)
+        .bind(product_id)
+        .bind(location_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(quantity.unwrap_or(0))
+    }
+
+    async fn get_catalog_entries(&self, tenant_id: Uuid, supplier_id: Uuid, product_id: Uuid) -> Result<Vec<SupplierCatalogEntry>> {
+        let rows = sqlx::query(
+            "SELECT * FROM supplier_catalog_entries \
+             WHERE tenant_id = $1 AND supplier_id = $2 AND product_id = $3 ORDER BY valid_from DESC",
+        )
+        .bind(tenant_id)
+        .bind(supplier_id)
+        .bind(product_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(map_catalog_entry).collect()
+    }
+
+    async fn record(&self, tenant_id: Uuid, movements: &[ConsignmentMovement], stock: &[InventoryMovement]) -> Result<()> {
+        for movement in movements {
+            ensure_actor(movement.created_by)?;
+        }
+        let mut tx = self.pool.begin().await?;
+
+        for movement in movements {
+            let change = movement.movement.balance_change(movement.quantity);
+            let updated = sqlx::query(
+                r#"
+                INSERT INTO consignment_stock (tenant_id, supplier_id, product_id, location_id, quantity, updated_at)
+                VALUES ($1, $2, $3, $4, $5, NOW())
+                ON CONFLICT (tenant_id, product_id, location_id, supplier_id)
+                DO UPDATE SET quantity = consignment_stock.quantity + EXCLUDED.quantity, updated_at = NOW()
+                WHERE consignment_stock.quantity + EXCLUDED.quantity >= 0
+                "#,
+            )
+            .bind(tenant_id)
+            .bind(movement.supplier_id)
+            .bind(movement.product_id)
+            .bind(movement.location_id)
+            .bind(change)
+            .execute(&mut *tx)
+            .await;
+            // A new row with a negative quantity fails its check constraint
+            let updated = match updated {
+                Ok(result) => result.rows_affected(),
+                Err(sqlx::Error::Database(e)) if e.is_check_violation() => 0,
+                Err(e) => return Err(e.into()),
+            };
+            if updated == 0 {
+                return Err(MasterDataError::ValidationError {
+                    field: "quantity".to_string(),
+                    message: "The supplier's consignment stock changed in the meantime".to_string(),
+                });
+            }
+
+            sqlx::query(
+                r#"
+                INSERT INTO consignment_movements (
+                    id, tenant_id, supplier_id, product_id, location_id, movement, quantity,
+                    unit_price, currency, catalog_entry_id, reference, created_by, created_at
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+                "#,
+            )
+            .bind(movement.id)
+            .bind(tenant_id)
+            .bind(movement.supplier_id)
+            .bind(movement.product_id)
+            .bind(movement.location_id)
+            .bind(movement.movement.as_str())
+            .bind(movement.quantity)
+            .bind(movement.unit_price)
+            .bind(&movement.currency)
+            .bind(movement.catalog_entry_id)
+            .bind(&movement.reference)
+            .bind(movement.created_by)
+            .bind(movement.created_at)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        for movement in stock {
+            apply_stock_movement(&mut tx, movement).await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn list_balances(&self, tenant_id: Uuid, supplier_id: Option<Uuid>) -> Result<Vec<ConsignmentBalance>> {
+        let rows = sqlx::query(
+            "SELECT supplier_id, product_id, location_id, quantity FROM consignment_stock \
+             WHERE tenant_id = $1 AND quantity > 0 AND ($2::UUID IS NULL OR supplier_id = $2) \
+             ORDER BY supplier_id, product_id, location_id",
+        )
+        .bind(tenant_id)
+        .bind(supplier_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                Ok(ConsignmentBalance {
+                    supplier_id: row.try_get("supplier_id")?,
+                    product_id: row.try_get("product_id")?,
+                    location_id: row.try_get("location_id")?,
+                    quantity: row.try_get("quantity")?,
+                })
+            })
+            .collect()
+    }
+
+    async fn list_movements(&self, tenant_id: Uuid, supplier_id: Uuid, from: NaiveDate, to: NaiveDate) -> Result<Vec<ConsignmentMovement>> {
+        let rows = sqlx::query(
+            "SELECT id, supplier_id, product_id, location_id, movement, quantity, unit_price, currency::TEXT AS currency, \
+                    catalog_entry_id, reference, created_by, created_at \
+             FROM consignment_movements \
+             WHERE tenant_id = $1 AND supplier_id = $2 AND created_at >= $3::DATE AND created_at < $4::DATE + 1 \
+             ORDER BY created_at",
+        )
+        .bind(tenant_id)
+        .bind(supplier_id)
+        .bind(from)
+        .bind(to)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                let movement: String = row.try_get("movement")?;
+                Ok(ConsignmentMovement {
+                    id: row.try_get("id")?,
+                    supplier_id: row.try_get("supplier_id")?,
+                    product_id: row.try_get("product_id")?,
+                    location_id: row.try_get("location_id")?,
+                    movement: ConsignmentMovementKind::parse(&movement).ok_or_else(|| MasterDataError::Internal {
+                        message: format!("Unknown consignment movement {}", movement),
+                    })?,
+                    quantity: row.try_get("quantity")?,
+                    unit_price: row.try_get("unit_price")?,
+                    currency: row.try_get("currency")?,
+                    catalog_entry_id: row.try_get("catalog_entry_id")?,
+                    reference: row.try_get("reference")?,
+                    created_by: row.try_get("created_by")?,
+                    created_at: row.try_get("created_at")?,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Reads the inputs of available-to-promise (see [`crate::inventory::atp`])
+#[async_trait]
+pub trait AvailableToPromiseRepository: Send + Sync {
+    /// Fails with `ProductNotFound` unless the product belongs to the tenant,
+    /// and with `LocationNotFound` for a location outside the tenant
+    async fn check_references(&self, tenant_id: Uuid, product_ids: &[Uuid], location_ids: &[Uuid]) -> Result<()>;
+    /// Stock of the product at the location, or at every location of the tenant
+    async fn get_stock(&self, tenant_id: Uuid, product_id: Uuid, location_id: Option<Uuid>) -> Result<Vec<AtpStock>>;
+    /// Outstanding purchase order lines and inbound transfers of the product,
+    /// in whatever status; the calculation decides what counts
+    async fn get_scheduled_receipts(
+        &self,
+        tenant_id: Uuid,
+        product_id: Uuid,
+        location_id: Option<Uuid>,
+    ) -> Result<Vec<ScheduledReceipt>>;
+}
+
+pub struct PostgresAvailableToPromiseRepository {
+    pool: Pool<Postgres>,
+    /// Location types whose stock is never promised
+    quarantine_location_types: Vec<String>,
+}
+
+impl PostgresAvailableToPromiseRepository {
+    pub fn new(pool: Pool<Postgres>) -> Self {
+        Self {
+            pool,
+            quarantine_location_types: vec!["quarantine".to_string()],
+        }
+    }
+
+    pub fn with_quarantine_location_types(mut self, location_types: Vec<String>) -> Self {
+        self.quarantine_location_types = location_types;
+        self
+    }
+}
+
+#[async_trait]
+impl AvailableToPromiseRepository for PostgresAvailableToPromiseRepository {
+    async fn check_references(&self, tenant_id: Uuid, product_ids: &[Uuid], location_ids: &[Uuid]) -> Result<()> {
+        let row = sqlx::query(
+            "SELECT ARRAY(SELECT wanted FROM UNNEST($2::uuid[]) AS wanted \
+                          WHERE NOT EXISTS (SELECT 1 FROM products WHERE id = wanted AND tenant_id = $1)) AS missing_products, \
+                    ARRAY(SELECT wanted FROM UNNEST($3::uuid[]) AS wanted \
+                          WHERE NOT EXISTS (SELECT 1 FROM locations WHERE id = wanted AND tenant_id = $1)) AS missing_locations",
+        )
+        .bind(tenant_id)
+        .bind(product_ids)
+        .bind(location_ids)
+        .fetch_one(&self.pool)
+        .await?;
+
+        let missing: Vec<Uuid> = row.try_get("missing_products")?;
+        if let Some(product_id) = missing.first() {
+            return Err(MasterDataError::ProductNotFound { id: product_id.to_string() });
+        }
+        let missing: Vec<Uuid> = row.try_get("missing_locations")?;
+        if let Some(location_id) = missing.first() {
+            return Err(MasterDataError::LocationNotFound { id: location_id.to_string() });
+        }
+        Ok(())
+    }
+
+    async fn get_stock(&self, tenant_id: Uuid, product_id: Uuid, location_id: Option<Uuid>) -> Result<Vec<AtpStock>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT li.location_id, li.quantity_available::bigint AS quantity_available,
+                   li.quantity_reserved::bigint AS quantity_reserved,
+                   COALESCE((SELECT SUM(pb.quantity) FROM public.product_batches pb
+                             WHERE pb.product_id = li.product_id AND pb.location_id = li.location_id
+                               AND pb.quality_status IN ('failed', 'recalled') AND pb.disposition IS NULL), 0)::bigint
+                       AS quantity_blocked,
+                   li.location_type::text <> ALL($4::text[]) AS sellable
+            FROM location_items li
+            JOIN locations l ON l.id = li.location_id
+            WHERE l.tenant_id = $1 AND li.product_id = $2 AND ($3::uuid IS NULL OR li.location_id = $3)
+            ORDER BY li.location_id
+            "#,
+        )
+        .bind(tenant_id)
+        .bind(product_id)
+        .bind(location_id)
+        .bind(&self.quarantine_location_types)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                Ok(AtpStock {
+                    location_id: row.try_get("location_id")?,
+                    quantity_available: row.try_get("quantity_available")?,
+                    quantity_reserved: row.try_get("quantity_reserved")?,
+                    quantity_blocked: row.try_get("quantity_blocked")?,
+                    sellable: row.try_get("sellable")?,
+                })
+            })
+            .collect()
+    }
+
+    async fn get_scheduled_receipts(
+        &self,
+        tenant_id: Uuid,
+        product_id: Uuid,
+        location_id: Option<Uuid>,
+    ) -> Result<Vec<ScheduledReceipt>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT 'purchase_order' AS source, po.id AS reference_id, po.location_id, po.status::text AS status,
+                   SUM(pol.quantity_ordered - pol.quantity_received)::bigint AS quantity,
+                   po.expected_delivery_date::date AS expected_date,
+                   l.location_type::text <> ALL($4::text[]) AS sellable
+            FROM purchase_orders po
+            JOIN purchase_order_lines pol ON pol.purchase_order_id = po.id
+            JOIN locations l ON l.id = po.location_id
+            WHERE l.tenant_id = $1 AND pol.product_id = $2 AND ($3::uuid IS NULL OR po.location_id = $3)
+              AND pol.quantity_ordered > pol.quantity_received AND po.status::text <> 'received'
+            GROUP BY po.id, po.location_id, po.status, po.expected_delivery_date, l.location_type
+            UNION ALL
+            SELECT 'transfer', st.id, st.to_location_id, st.status::text,
+                   (COALESCE(st.quantity_shipped, st.quantity) - COALESCE(st.quantity_received, 0))::bigint,
+                   st.expected_arrival_date,
+                   l.location_type::text <> ALL($4::text[])
+            FROM stock_transfers st
+            JOIN locations l ON l.id = st.to_location_id
+            WHERE l.tenant_id = $1 AND st.product_id = $2 AND ($3::uuid IS NULL OR st.to_location_id = $3)
+              AND COALESCE(st.quantity_shipped, st.quantity) > COALESCE(st.quantity_received, 0)
+              AND st.status::text NOT IN ('completed', 'cancelled', 'rejected')
+            ORDER BY expected_date NULLS LAST, reference_id
+            "#,
+        )
+        .bind(tenant_id)
+        .bind(product_id)
+        .bind(location_id)
+        .bind(&self.quarantine_location_types)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                let source: String = row.try_get("source")?;
+                Ok(ScheduledReceipt {
+                    source: if source == "transfer" { SupplySource::Transfer } else { SupplySource::PurchaseOrder },
+                    reference_id: row.try_get("reference_id")?,
+                    location_id: row.try_get("location_id")?,
+                    status: row.try_get("status")?,
+                    quantity: row.try_get("quantity")?,
+                    expected_date: row.try_get("expected_date")?,
+                    sellable: row.try_get("sellable")?,
+                })
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+pub trait InventorySnapshotRepository: Send + Sync {
+    /// Record the stock of the location's products in the scope as a new
+    /// snapshot; fails with `LocationNotFound` unless the location belongs to
+    /// the tenant
+    async fn create_snapshot(
+        &self,
+        tenant_id: Uuid,
+        location_id: Uuid,
+        snapshot_date: NaiveDate,
+        scope: &SnapshotScope,
+        trigger: SnapshotTrigger,
+        created_by: Option<Uuid>,
+    ) -> Result<SnapshotRun>;
+    async fn get_snapshot(&self, tenant_id: Uuid, snapshot_id: Uuid) -> Result<Option<SnapshotRun>>;
+    /// Snapshots of the tenant, optionally of one location, newest first
+    async fn list_snapshots(&self, tenant_id: Uuid, location_id: Option<Uuid>, limit: i64) -> Result<Vec<SnapshotRun>>;
+    async fn get_snapshot_lines(&self, tenant_id: Uuid, snapshot_id: Uuid) -> Result<Vec<SnapshotLine>>;
+    /// Month-end schedules of the tenant, or of every tenant
+    async fn list_schedules(&self, tenant_id: Option<Uuid>) -> Result<Vec<SnapshotSchedule>>;
+    /// Create or replace the schedule of the location; fails with
+    /// `LocationNotFound` unless the location belongs to the tenant
+    async fn save_schedule(&self, schedule: &SnapshotSchedule) -> Result<SnapshotSchedule>;
+    async fn delete_schedule(&self, tenant_id: Uuid, location_id: Uuid) -> Result<bool>;
+    /// Note that the month-end snapshot of `month_end` was taken
+    async fn mark_schedule_run(&self, tenant_id: Uuid, location_id: Uuid, month_end: NaiveDate) -> Result<()>;
+}
+
+pub struct PostgresInventorySnapshotRepository {
+    pool: Pool<Postgres>,
+}
+
+impl PostgresInventorySnapshotRepository {
+    pub fn new(pool: Pool<Postgres>) -> Self {
+        Self { pool }
+    }
+
+    async fn check_location(&self, tenant_id: Uuid, location_id: Uuid) -> Result<()> {
+        let exists: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM locations WHERE id = $1 AND tenant_id = $2)")
+            .bind(location_id)
+            .bind(tenant_id)
+            .fetch_one(&self.pool)
+            .await?;
+        if !exists {
+            return Err(MasterDataError::LocationNotFound { id: location_id.to_string() });
+        }
+        Ok(())
+    }
+}
+
+const SNAPSHOT_RUN_COLUMNS: &str =
+    "id, tenant_id, location_id, snapshot_date, scope, is_partial, trigger, line_count, total_value, created_by, created_at";
+
+fn scope_from_json(value: serde_json::Value) -> Result<SnapshotScope> {
+    Ok(serde_json::from_value(value)?)
+}
+
+fn snapshot_run_from_row(row: &sqlx::postgres::PgRow) -> Result<SnapshotRun> {
+    let trigger: String = row.try_get("trigger")?;
+    let line_count: i32 = row.try_get("line_count")?;
+    Ok(SnapshotRun {
+        id: row.try_get("id")?,
+        tenant_id: row.try_get("tenant_id")?,
+        location_id: row.try_get("location_id")?,
+        snapshot_date: row.try_get("snapshot_date")?,
+        scope: scope_from_json(row.try_get("scope")?)?,
+        partial: row.try_get("is_partial")?,
+        trigger: SnapshotTrigger::parse(&trigger).unwrap_or(SnapshotTrigger::Manual),
+        line_count: line_count.into(),
+        total_value: row.try_get("total_value")?,
+        created_by: row.try_get("created_by")?,
+        created_at: row.try_get("created_at")?,
+    })
+}
+
+fn snapshot_schedule_from_row(row: &sqlx::postgres::PgRow) -> Result<SnapshotSchedule> {
+    Ok(SnapshotSchedule {
+        tenant_id: row.try_get("tenant_id")?,
+        location_id: row.try_get("location_id")?,
+        scope: scope_from_json(row.try_get("scope")?)?,
+        enabled: row.try_get("enabled")?,
+        last_run_on: row.try_get("last_run_on")?,
+        updated_by: row.try_get("updated_by")?,
+        updated_at: row.try_get("updated_at")?,
+    })
+}
+
+#[async_trait]
+impl InventorySnapshotRepository for PostgresInventorySnapshotRepository {
+    async fn create_snapshot(
+        &self,
+        tenant_id: Uuid,
+        location_id: Uuid,
+        snapshot_date: NaiveDate,
+        scope: &SnapshotScope,
+        trigger: SnapshotTrigger,
+        created_by: Option<Uuid>,
+    ) -> Result<SnapshotRun> {
+        created_by.map(ensure_actor).transpose()?;
+        self.check_location(tenant_id, location_id).await?;
+        let scope_json = serde_json::to_value(scope)?;
+        let mut tx = self.pool.begin().await?;
+
+        let run_id: Uuid = sqlx::query_scalar(
+            "INSERT INTO public.inventory_snapshot_runs \
+                 (tenant_id, location_id, snapshot_date, scope, is_partial, trigger, created_by) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING id",
+        )
+        .bind(tenant_id)
+        .bind(location_id)
+        .bind(snapshot_date)
+        .bind(&scope_json)
+        .bind(!scope.is_full())
+        .bind(trigger.as_str())
+        .bind(created_by)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        // Only the conditions of the scope are added, so the location and
+        // product indexes narrow the read to the products in scope
+        let mut query = sqlx::QueryBuilder::new(
+            "INSERT INTO inventory_snapshots \
+                 (run_id, snapshot_date, snapshot_type, product_id, location_id, category_id, abc_classification, \
+                  quantity_available, quantity_reserved, quantity_on_order, quantity_in_transit, unit_cost, total_value) \
+             SELECT ",
+        );
+        query
+            .push_bind(run_id)
+            .push(", ")
+            .push_bind(snapshot_date)
+            .push(", ")
+            .push_bind(if scope.is_full() { "full" } else { "partial" })
+            .push(
+                ", li.product_id, li.location_id, p.category_id, li.abc_classification::text, \
+                 GREATEST(li.quantity_available, 0), GREATEST(li.quantity_reserved, 0), \
+                 GREATEST(li.quantity_on_order, 0), GREATEST(li.quantity_in_transit, 0), \
+                 p.cost_price / 100.0, GREATEST(li.quantity_available, 0) * COALESCE(p.cost_price, 0) / 100.0 \
+                 FROM location_items li JOIN products p ON p.id = li.product_id AND p.tenant_id = ",
+            )
+            .push_bind(tenant_id)
+            .push(" WHERE li.location_id = ")
+            .push_bind(location_id);
+        if !scope.product_ids.is_empty() {
+            query.push(" AND li.product_id = ANY(").push_bind(&scope.product_ids).push(")");
+        }
+        if let Some(category_id) = scope.category_id {
+            query.push(" AND p.category_id = ").push_bind(category_id);
+        }
+        if let Some(class) = &scope.abc_class {
+            query
+                .push(" AND li.abc_classification = CAST(")
+                .push_bind(abc_label(class))
+                .push(" AS abc_classification)");
+        }
+        if let Some(min_unit_value) = scope.min_unit_value {
+            query.push(" AND p.cost_price >= ").push_bind(min_unit_value);
+        }
+        query.build().execute(&mut *tx).await?;
+
+        let row = sqlx::query(&format!(
+            "UPDATE public.inventory_snapshot_runs r \
+             SET line_count = totals.line_count, total_value = totals.total_value \
+             FROM (SELECT COUNT(*)::INT AS line_count, COALESCE(SUM(total_value * 100), 0)::BIGINT AS total_value \
+                   FROM inventory_snapshots WHERE run_id = $1) totals \
+             WHERE r.id = $1 RETURNING {}",
+            SNAPSHOT_RUN_COLUMNS
+                .split(", ")
+                .map(|column| format!("r.{}", column))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ))
+        .bind(run_id)
+        .fetch_one(&mut *tx)
+        .await?;
+        let run = snapshot_run_from_row(&row)?;
+
+        tx.commit().await?;
+        Ok(run)
+    }
+
+    async fn get_snapshot(&self, tenant_id: Uuid, snapshot_id: Uuid) -> Result<Option<SnapshotRun>> {
+        let row = sqlx::query(&format!(
+            "SELECT {} FROM public.inventory_snapshot_runs WHERE id = $1 AND tenant_id = $2",
+            SNAPSHOT_RUN_COLUMNS
+        ))
+        .bind(snapshot_id)
+        .bind(tenant_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        row.as_ref().map(snapshot_run_from_row).transpose()
+    }
+
+    async fn list_snapshots(&self, tenant_id: Uuid, location_id: Option<Uuid>, limit: i64) -> Result<Vec<SnapshotRun>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM public.inventory_snapshot_runs \
+             WHERE tenant_id = $1 AND ($2::UUID IS NULL OR location_id = $2) \
+             ORDER BY created_at DESC, id LIMIT $3",
+            SNAPSHOT_RUN_COLUMNS
+        ))
+        .bind(tenant_id)
+        .bind(location_id)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        rows.iter().map(snapshot_run_from_row).collect()
+    }
+
+    async fn get_snapshot_lines(&self, tenant_id: Uuid, snapshot_id: Uuid) -> Result<Vec<SnapshotLine>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT s.product_id, s.category_id, s.abc_classification,
+                   s.quantity_available::BIGINT AS quantity_on_hand, s.quantity_reserved::BIGINT AS quantity_reserved,
+                   s.quantity_on_order::BIGINT AS quantity_on_order, s.quantity_in_transit::BIGINT AS quantity_in_transit,
+                   ROUND(s.unit_cost * 100)::BIGINT AS unit_cost, ROUND(COALESCE(s.total_value, 0) * 100)::BIGINT AS total_value
+            FROM inventory_snapshots s
+            JOIN public.inventory_snapshot_runs r ON r.id = s.run_id
+            WHERE s.run_id = $1 AND r.tenant_id = $2
+            ORDER BY s.product_id
+            "#,
+        )
+        .bind(snapshot_id)
+        .bind(tenant_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                let abc_class: Option<String> = row.try_get("abc_classification")?;
+                Ok(SnapshotLine {
+                    product_id: row.try_get("product_id")?,
+                    category_id: row.try_get("category_id")?,
+                    abc_class: abc_class.as_deref().and_then(parse_abc_label),
+                    quantity_on_hand: row.try_get("quantity_on_hand")?,
+                    quantity_reserved: row.try_get("quantity_reserved")?,
+                    quantity_on_order: row.try_get("quantity_on_order")?,
+                    quantity_in_transit: row.try_get("quantity_in_transit")?,
+                    unit_cost: row.try_get("unit_cost")?,
+                    total_value: row.try_get("total_value")?,
+                })
+            })
+            .collect()
+    }
+
+    async fn list_schedules(&self, tenant_id: Option<Uuid>) -> Result<Vec<SnapshotSchedule>> {
+        let rows = sqlx::query(
+            "SELECT tenant_id, location_id, scope, enabled, last_run_on, updated_by, updated_at \
+             FROM public.inventory_snapshot_schedules \
+             WHERE $1::UUID IS NULL OR tenant_id = $1 \
+             ORDER BY tenant_id, location_id",
+        )
+        .bind(tenant_id)
+        .fetch_all(&self.pool)
+        .await?;
+        rows.iter().map(snapshot_schedule_from_row).collect()
+    }
+
+    async fn save_schedule(&self, schedule: &SnapshotSchedule) -> Result<SnapshotSchedule> {
+        schedule.updated_by.map(ensure_actor).transpose()?;
+        self.check_location(schedule.tenant_id, schedule.location_id).await?;
+        let scope_json = serde_json::to_value(&schedule.scope)?;
+        let row = sqlx::query(
+            "INSERT INTO public.inventory_snapshot_schedules \
+                 (tenant_id, location_id, scope, enabled, updated_by, updated_at) \
+             VALUES ($1, $2, $3, $4, $5, NOW()) \
+             ON CONFLICT (tenant_id, location_id) DO UPDATE \
+             SET scope = EXCLUDED.scope, enabled = EXCLUDED.enabled, \
+                 updated_by = EXCLUDED.updated_by, updated_at = EXCLUDED.updated_at \
+             RETURNING tenant_id, location_id, scope, enabled, last_run_on, updated_by, updated_at",
+        )
+        .bind(schedule.tenant_id)
+        .bind(schedule.location_id)
+        .bind(&scope_json)
+        .bind(schedule.enabled)
+        .bind(schedule.updated_by)
+        .fetch_one(&self.pool)
+        .await?;
+        snapshot_schedule_from_row(&row)
+    }
+
+    async fn delete_schedule(&self, tenant_id: Uuid, location_id: Uuid) -> Result<bool> {
+        let result =
+            sqlx::query("DELETE FROM public.inventory_snapshot_schedules WHERE tenant_id = $1 AND location_id = $2")
+                .bind(tenant_id)
+                .bind(location_id)
+                .execute(&self.pool)
+                .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn mark_schedule_run(&self, tenant_id: Uuid, location_id: Uuid, month_end: NaiveDate) -> Result<()> {
+        sqlx::query(
+            "UPDATE public.inventory_snapshot_schedules SET last_run_on = $3 WHERE tenant_id = $1 AND location_id = $2",
+        )
+        .bind(tenant_id)
+        .bind(location_id)
+        .bind(month_end)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
+
+const COSTED_MOVEMENT_COLUMNS: &str = "t.id, t.product_id, t.location_id, t.transaction_type::text AS movement_type, \
+    t.quantity_change, t.unit_cost, t.cost_source, t.cost_needs_review, t.reversal_of, t.restatement_of, \
+    t.reference_document, t.reference_number, t.reason_code, t.transaction_date";
+
+fn costed_movement_from_row(row: &sqlx::postgres::PgRow) -> Result<CostedMovement> {
+    let cost_source: Option<String> = row.try_get("cost_source")?;
+    Ok(CostedMovement {
+        id: row.try_get("id")?,
+        product_id: row.try_get("product_id")?,
+        location_id: row.try_get("location_id")?,
+        movement_type: row.try_get("movement_type")?,
+        quantity: row.try_get("quantity_change")?,
+        unit_cost: row.try_get("unit_cost")?,
+        cost_source: cost_source.as_deref().and_then(CostSource::parse),
+        cost_needs_review: row.try_get("cost_needs_review")?,
+        reversal_of: row.try_get("reversal_of")?,
+        restatement_of: row.try_get("restatement_of")?,
+        reference_document: row.try_get("reference_document")?,
+        reference_number: row.try_get("reference_number")?,
+        reason: row.try_get("reason_code")?,
+        occurred_at: row.try_get("transaction_date")?,
+    })
+}
+
+#[async_trait]
+pub trait InventoryCostingRepository: Send + Sync {
+    /// The tenant's settings, the defaults until it saved its own
+    async fn get_settings(&self, tenant_id: Uuid) -> Result<CostingSettings>;
+    async fn save_settings(&self, tenant_id: Uuid, settings: &CostingSettings, updated_by: Uuid) -> Result<CostingSettings>;
+    /// Movements of the tenant's products from `from` to `to`, inclusive
+    async fn list_movements(&self, tenant_id: Uuid, from: NaiveDate, to: NaiveDate) -> Result<Vec<CostedMovement>>;
+    /// The movement, and whether it was reversed
+    async fn get_movement(&self, tenant_id: Uuid, movement_id: Uuid) -> Result<Option<(CostedMovement, bool)>>;
+    /// Store the reversal and the re-booking; the quantity on hand stays as
+    /// it is. Fails with a `ValidationError` when the movement was reversed
+    /// in the meantime.
+    async fn record_restatement(&self, restatement: &CostRestatement, operator_id: Uuid) -> Result<()>;
+}
+
+pub struct PostgresInventoryCostingRepository {
+    pool: Pool<Postgres>,
+}
+
+impl PostgresInventoryCostingRepository {
+    pub fn new(pool: Pool<Postgres>) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl InventoryCostingRepository for PostgresInventoryCostingRepository {
+    async fn get_settings(&self, tenant_id: Uuid) -> Result<CostingSettings> {
+        let row = sqlx::query(
+            "SELECT cost_source_chain, strict_costing FROM public.inventory_costing_settings WHERE tenant_id = $1",
+        )
+        .bind(tenant_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        match row {
+            Some(row) => Ok(map_costing_settings(row.try_get("cost_source_chain")?, row.try_get("strict_costing")?)),
+            None => Ok(CostingSettings::default()),
+        }
+    }
+
+    async fn save_settings(&self, tenant_id: Uuid, settings: &CostingSettings, updated_by: Uuid) -> Result<CostingSettings> {
+        ensure_actor(updated_by)?;
+        let chain: Vec<&str> = settings.cost_source_chain.iter().map(CostSource::as_str).collect();
+        let row = sqlx::query(
+            "INSERT INTO public.inventory_costing_settings (tenant_id, cost_source_chain, strict_costing, updated_by, updated_at) \
+             VALUES ($1, $2, $3, $4, NOW()) \
+             ON CONFLICT (tenant_id) DO UPDATE \
+             SET cost_source_chain = EXCLUDED.cost_source_chain, strict_costing = EXCLUDED.strict_costing, \
+                 updated_by = EXCLUDED.updated_by, updated_at = EXCLUDED.updated_at \
+             RETURNING cost_source_chain, strict_costing",
+        )
+        .bind(tenant_id)
+        .bind(&chain)
+        .bind(settings.strict_costing)
+        .bind(updated_by)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(map_costing_settings(row.try_get("cost_source_chain")?, row.try_get("strict_costing")?))
+    }
+
+    async fn list_movements(&self, tenant_id: Uuid, from: NaiveDate, to: NaiveDate) -> Result<Vec<CostedMovement>> {
+        let rows = sqlx::query(&format!(
+            "SELECT {} FROM inventory_transactions t JOIN products p ON p.id = t.product_id \
+             WHERE p.tenant_id = $1 AND t.transaction_date >= $2::date AND t.transaction_date < $3::date + 1 \
+             ORDER BY t.transaction_date, t.id",
+            COSTED_MOVEMENT_COLUMNS
+        ))
+        .bind(tenant_id)
+        .bind(from)
+        .bind(to)
+        .fetch_all(&self.pool)
+        .await?;
+        rows.iter().map(costed_movement_from_row).collect()
+    }
+
+    async fn get_movement(&self, tenant_id: Uuid, movement_id: Uuid) -> Result<Option<(CostedMovement, bool)>> {
+        let row = sqlx::query(&format!(
+            "SELECT {}, EXISTS (SELECT 1 FROM inventory_transactions r WHERE r.reversal_of = t.id) AS reversed \
+             FROM inventory_transactions t JOIN products p ON p.id = t.product_id \
+             WHERE t.id = $1 AND p.tenant_id = $2",
+            COSTED_MOVEMENT_COLUMNS
+        ))
+        .bind(movement_id)
+        .bind(tenant_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        row.map(|row| Ok((costed_movement_from_row(&row)?, row.try_get("reversed")?))).transpose()
+    }
+
+    async fn record_restatement(&self, restatement: &CostRestatement, operator_id: Uuid) -> Result<()> {
+        ensure_actor(operator_id)?;
+        let mut tx = self.pool.begin().await?;
+
+        for movement in [&restatement.reversal, &restatement.rebooking] {
+            sqlx::query(
+                r#"
+                INSERT INTO inventory_transactions (
+                    id, transaction_number, transaction_type, product_id, location_id, quantity_change,
+                    unit_cost, cost_source, cost_needs_review, reversal_of, restatement_of,
+                    reference_document, reference_number, reason_code, created_by, created_at, transaction_date
+                )
+                VALUES ($1, CONCAT('TXN-', EXTRACT(EPOCH FROM NOW())), $2::movement_type, $3, $4, $5,
+                        $6, $7, $8, $9, $10, $11, $12, $13, $14, NOW(), $15)
+                "#,
+            )
+            .bind(movement.id)
+            .bind(&movement.movement_type)
+            .bind(movement.product_id)
+            .bind(movement.location_id)
+            .bind(movement.quantity)
+            .bind(movement.unit_cost)
+            .bind(movement.cost_source.map(|source| source.as_str()))
+            .bind(movement.cost_needs_review)
+            .bind(movement.reversal_of)
+            .bind(movement.restatement_of)
+            .bind(&movement.reference_document)
+            .bind(&movement.reference_number)
+            .bind(&movement.reason)
+            .bind(operator_id)
+            .bind(movement.occurred_at)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| match &e {
+                sqlx::Error::Database(db) if db.is_unique_violation() => MasterDataError::ValidationError {
+                    field: "movement_id".to_string(),
+                    message: format!("Movement {} was restated already", restatement.movement_id),
+                },
+                _ => MasterDataError::Database(e),
+            })?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+}
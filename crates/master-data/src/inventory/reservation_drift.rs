@@ -0,0 +1,292 @@
+//! # Reservation Drift
+//!
+//! `location_items.quantity_reserved` is kept in step with the stock held for
+//! an item by incrementing and decrementing it alongside the holds. Two kinds
+//! of hold exist:
+//!
+//! - active rows of `stock_reservations`
+//! - stock transfers that reserved their quantity at the source and have not
+//!   shipped it yet (see [`HOLDING_TRANSFER_STATUSES`])
+//!
+//! A code path that misses one side makes the column drift. Reconciliation
+//! compares the column with the sum of the holds per item; in repair mode it
+//! sets the column to that sum.
+//!
+//! ## Repairs
+//!
+//! A repair locks the `location_items` row first and sums the holds after, so
+//! a reservation committed meanwhile is counted exactly once: in the sum if it
+//! committed before the lock, by its own increment otherwise. The drift found
+//! by the detection query is therefore only a hint; [`plan_repair`] decides on
+//! the figures read under the lock. A correction that would reserve more than
+//! is on hand is refused and the item left for review.
+//!
+//! Every correction is stored as a [`ReservationAdjustment`] in
+//! `public.reservation_adjustments`; the column is never changed silently.
+//!
+//! ## Schedule
+//!
+//! The nightly run checks one of [`SWEEP_SLICES`] slices of the items, one
+//! slice per weekday, and every item on the weekly full sweep day (see
+//! [`ReconciliationScope::for_night`]). [`sweep_slice`] assigns the slices;
+//! the detection query uses the same arithmetic on the last two bytes of the
+//! item id.
+//!
+//! ## Alerts
+//!
+//! Drift that comes back after being corrected points to a code path that is
+//! still broken. [`is_repeated_drift`] tells when a correction of at least the
+//! alert threshold follows enough earlier ones on the same item.
+
+use chrono::{DateTime, Datelike, NaiveDate, Utc, Weekday};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Transfer statuses in which the source still holds the transfer quantity
+pub const HOLDING_TRANSFER_STATUSES: &[&str] = &["pending_approval", "requested", "approved", "pending"];
+
+/// Slices checked on the six nights between two full sweeps
+pub const SWEEP_SLICES: u32 = 6;
+
+/// Slice of the nightly rotation an item belongs to
+pub fn sweep_slice(location_item_id: Uuid) -> u32 {
+    let bytes = location_item_id.as_bytes();
+    u32::from(u16::from_be_bytes([bytes[14], bytes[15]])) % SWEEP_SLICES
+}
+
+/// Which items a reconciliation run checks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ReconciliationScope {
+    Full,
+    Slice { slice: u32 },
+}
+
+impl ReconciliationScope {
+    /// Every item on `full_sweep_day`, otherwise the slice of the weekday:
+    /// slice 0 the day after the full sweep, slice 5 the day before it
+    pub fn for_night(date: NaiveDate, full_sweep_day: Weekday) -> Self {
+        let days_after_sweep =
+            (date.weekday().num_days_from_monday() + 7 - full_sweep_day.num_days_from_monday()) % 7;
+        match days_after_sweep {
+            0 => ReconciliationScope::Full,
+            days => ReconciliationScope::Slice { slice: days - 1 },
+        }
+    }
+
+    pub fn includes(&self, location_item_id: Uuid) -> bool {
+        match self {
+            ReconciliationScope::Full => true,
+            ReconciliationScope::Slice { slice } => sweep_slice(location_item_id) == *slice,
+        }
+    }
+
+    /// The slice, or `None` for a full sweep
+    pub fn slice(&self) -> Option<u32> {
+        match self {
+            ReconciliationScope::Full => None,
+            ReconciliationScope::Slice { slice } => Some(*slice),
+        }
+    }
+}
+
+/// Reserved quantity of an item as stored and as held
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReservationDrift {
+    pub tenant_id: Uuid,
+    pub location_item_id: Uuid,
+    pub product_id: Uuid,
+    pub location_id: Uuid,
+    pub quantity_available: i32,
+    /// `quantity_reserved` as stored
+    pub recorded_reserved: i32,
+    /// Active reservations plus unshipped transfer quantities
+    pub held_reserved: i32,
+}
+
+impl ReservationDrift {
+    /// What the column is missing; negative when it reserves too much
+    pub fn delta(&self) -> i32 {
+        self.held_reserved - self.recorded_reserved
+    }
+
+    pub fn has_drift(&self) -> bool {
+        self.delta() != 0
+    }
+}
+
+/// A correction of `quantity_reserved`, kept for audit
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReservationAdjustment {
+    pub id: Uuid,
+    pub tenant_id: Uuid,
+    pub location_item_id: Uuid,
+    pub product_id: Uuid,
+    pub location_id: Uuid,
+    pub previous_reserved: i32,
+    pub corrected_reserved: i32,
+    /// Reconciliation run that made the correction
+    pub run_id: Uuid,
+    /// `None` when the nightly job corrected it
+    pub corrected_by: Option<Uuid>,
+    pub corrected_at: DateTime<Utc>,
+}
+
+impl ReservationAdjustment {
+    pub fn delta(&self) -> i32 {
+        self.corrected_reserved - self.previous_reserved
+    }
+}
+
+/// What a repair did with one item
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum RepairOutcome {
+    /// The column matched the holds once the item was locked
+    InSync,
+    Corrected { adjustment: ReservationAdjustment },
+    /// The holds exceed the stock on hand; nothing was changed
+    Refused { drift: ReservationDrift, reason: String },
+}
+
+/// Decide the repair of an item from figures read while it was locked
+pub fn plan_repair(
+    locked: &ReservationDrift,
+    run_id: Uuid,
+    corrected_by: Option<Uuid>,
+    now: DateTime<Utc>,
+) -> RepairOutcome {
+    if !locked.has_drift() {
+        return RepairOutcome::InSync;
+    }
+    if let Err(reason) = check_availability(locked.quantity_available, locked.held_reserved) {
+        return RepairOutcome::Refused {
+            drift: locked.clone(),
+            reason,
+        };
+    }
+
+    RepairOutcome::Corrected {
+        adjustment: ReservationAdjustment {
+            id: Uuid::new_v4(),
+            tenant_id: locked.tenant_id,
+            location_item_id: locked.location_item_id,
+            product_id: locked.product_id,
+            location_id: locked.location_id,
+            previous_reserved: locked.recorded_reserved,
+            corrected_reserved: locked.held_reserved,
+            run_id,
+            corrected_by,
+            corrected_at: now,
+        },
+    }
+}
+
+/// The invariants `location_items` must keep: nothing negative and no more
+/// reserved than is on hand
+pub fn check_availability(quantity_available: i32, quantity_reserved: i32) -> Result<(), String> {
+    if quantity_reserved < 0 {
+        return Err(format!("reserved quantity {} is negative", quantity_reserved));
+    }
+    if quantity_reserved > quantity_available {
+        return Err(format!(
+            "{} units are held but only {} are on hand",
+            quantity_reserved, quantity_available
+        ));
+    }
+    Ok(())
+}
+
+/// Whether a correction of `delta` is drift coming back: at least `threshold`
+/// units, after `repeat_count - 1` earlier corrections of at least as much on
+/// the same item (`earlier_deltas`, from the alert window)
+pub fn is_repeated_drift(delta: i32, earlier_deltas: &[i32], threshold: u32, repeat_count: u32) -> bool {
+    let significant = |delta: i32| delta.unsigned_abs() >= threshold.max(1);
+    significant(delta)
+        && earlier_deltas.iter().filter(|earlier| significant(**earlier)).count() + 1 >= repeat_count.max(1) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(quantity_available: i32, recorded_reserved: i32, held_reserved: i32) -> ReservationDrift {
+        ReservationDrift {
+            tenant_id: Uuid::new_v4(),
+            location_item_id: Uuid::new_v4(),
+            product_id: Uuid::new_v4(),
+            location_id: Uuid::new_v4(),
+            quantity_available,
+            recorded_reserved,
+            held_reserved,
+        }
+    }
+
+    #[test]
+    fn test_rotating_slices_cover_every_item_once_per_week() {
+        let items: Vec<Uuid> = (0..500).map(|_| Uuid::new_v4()).collect();
+        // 2026-10-12 is a Monday; Sunday is the full sweep
+        let week: Vec<ReconciliationScope> = (12..19)
+            .map(|day| ReconciliationScope::for_night(NaiveDate::from_ymd_opt(2026, 10, day).unwrap(), Weekday::Sun))
+            .collect();
+
+        assert_eq!(week[0], ReconciliationScope::Slice { slice: 0 });
+        assert_eq!(week[5], ReconciliationScope::Slice { slice: 5 });
+        assert_eq!(week[6], ReconciliationScope::Full);
+
+        for id in &items {
+            let nights = week[..6].iter().filter(|scope| scope.includes(*id)).count();
+            assert_eq!(nights, 1, "item {} checked on {} slice nights", id, nights);
+            assert!(week[6].includes(*id));
+        }
+        // Every slice gets some of the items
+        for slice in 0..SWEEP_SLICES {
+            assert!(items.iter().any(|id| sweep_slice(*id) == slice));
+        }
+    }
+
+    #[test]
+    fn test_drift_is_detected_in_both_directions() {
+        assert!(!item(10, 4, 4).has_drift());
+        assert_eq!(item(10, 4, 7).delta(), 3);
+        assert_eq!(item(10, 9, 2).delta(), -7);
+    }
+
+    #[test]
+    fn test_repair_uses_locked_figures_and_keeps_invariants() {
+        let run_id = Uuid::new_v4();
+        let now = Utc::now();
+
+        // Detection saw 2 missing; a reservation of 5 committed before the lock
+        let locked = item(20, 8, 15);
+        match plan_repair(&locked, run_id, None, now) {
+            RepairOutcome::Corrected { adjustment } => {
+                assert_eq!(adjustment.previous_reserved, 8);
+                assert_eq!(adjustment.corrected_reserved, 15);
+                assert_eq!(adjustment.delta(), 7);
+                assert_eq!(adjustment.run_id, run_id);
+            }
+            other => panic!("expected a correction, got {:?}", other),
+        }
+
+        assert_eq!(plan_repair(&item(20, 6, 6), run_id, None, now), RepairOutcome::InSync);
+
+        // Holds beyond the stock on hand are left for review
+        assert!(matches!(
+            plan_repair(&item(10, 4, 12), run_id, None, now),
+            RepairOutcome::Refused { .. }
+        ));
+        assert!(check_availability(10, 10).is_ok());
+        assert!(check_availability(10, -1).is_err());
+    }
+
+    #[test]
+    fn test_repeated_drift_needs_enough_significant_corrections() {
+        // Threshold 5, third significant correction alerts
+        assert!(!is_repeated_drift(6, &[], 5, 3));
+        assert!(!is_repeated_drift(6, &[-8, 1, 2], 5, 3));
+        assert!(is_repeated_drift(-6, &[-8, 5], 5, 3));
+        // Small corrections never alert
+        assert!(!is_repeated_drift(4, &[9, 9, 9], 5, 3));
+    }
+}
@@ -0,0 +1,734 @@
+//! # Customer Returns
+//!
+//! A [`ReturnOrder`] records goods a customer sends back, one line per
+//! product with the quantity and a [`ReturnReason`].
+//!
+//! ## Lifecycle
+//!
+//! ```text
+//! requested ─► received ─► inspected
+//! requested ─► cancelled
+//! ```
+//!
+//! Receiving books the goods into a quarantine location, whose stock never
+//! counts as sellable availability. Inspection then decides a
+//! [`Disposition`] per line and moves the units out of quarantine:
+//!
+//! | Disposition          | Movements                                              |
+//! |----------------------|--------------------------------------------------------|
+//! | `restock`            | transfer from quarantine to a sellable location        |
+//! | `refurbish`          | transfer from quarantine to a non-sellable work area   |
+//! | `scrap`              | write-off from quarantine with the scrap reason        |
+//! | `return_to_supplier` | outbound shipment from quarantine to the supplier      |
+//!
+//! Lines may be inspected in several passes; the order is `inspected` once
+//! every line has a disposition.
+
+use crate::error::{MasterDataError, Result};
+use crate::inventory::model::InventoryMovement;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+/// Where a return order is in its lifecycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReturnStatus {
+    /// Announced by the customer; nothing booked yet
+    Requested,
+    /// In quarantine, awaiting inspection
+    Received,
+    /// Every line has a disposition
+    Inspected,
+    Cancelled,
+}
+
+impl ReturnStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ReturnStatus::Requested => "requested",
+            ReturnStatus::Received => "received",
+            ReturnStatus::Inspected => "inspected",
+            ReturnStatus::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "requested" => Some(ReturnStatus::Requested),
+            "received" => Some(ReturnStatus::Received),
+            "inspected" => Some(ReturnStatus::Inspected),
+            "cancelled" => Some(ReturnStatus::Cancelled),
+            _ => None,
+        }
+    }
+
+    /// Whether the lifecycle allows moving from `self` to `next`
+    pub fn can_transition_to(self, next: ReturnStatus) -> bool {
+        matches!(
+            (self, next),
+            (ReturnStatus::Requested, ReturnStatus::Received)
+                | (ReturnStatus::Requested, ReturnStatus::Cancelled)
+                | (ReturnStatus::Received, ReturnStatus::Inspected)
+        )
+    }
+}
+
+/// Why the customer sent a line back
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReturnReason {
+    Defective,
+    DamagedInTransit,
+    WrongItem,
+    NotAsDescribed,
+    NoLongerNeeded,
+    Other,
+}
+
+impl ReturnReason {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ReturnReason::Defective => "defective",
+            ReturnReason::DamagedInTransit => "damaged_in_transit",
+            ReturnReason::WrongItem => "wrong_item",
+            ReturnReason::NotAsDescribed => "not_as_described",
+            ReturnReason::NoLongerNeeded => "no_longer_needed",
+            ReturnReason::Other => "other",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "defective" => Some(ReturnReason::Defective),
+            "damaged_in_transit" => Some(ReturnReason::DamagedInTransit),
+            "wrong_item" => Some(ReturnReason::WrongItem),
+            "not_as_described" => Some(ReturnReason::NotAsDescribed),
+            "no_longer_needed" => Some(ReturnReason::NoLongerNeeded),
+            "other" => Some(ReturnReason::Other),
+            _ => None,
+        }
+    }
+}
+
+/// What inspection decided to do with a returned line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Disposition {
+    Restock,
+    Refurbish,
+    Scrap,
+    ReturnToSupplier,
+}
+
+impl Disposition {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Disposition::Restock => "restock",
+            Disposition::Refurbish => "refurbish",
+            Disposition::Scrap => "scrap",
+            Disposition::ReturnToSupplier => "return_to_supplier",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "restock" => Some(Disposition::Restock),
+            "refurbish" => Some(Disposition::Refurbish),
+            "scrap" => Some(Disposition::Scrap),
+            "return_to_supplier" => Some(Disposition::ReturnToSupplier),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReturnOrderLine {
+    pub id: Uuid,
+    pub product_id: Uuid,
+    pub quantity: i32,
+    pub reason: ReturnReason,
+    pub note: Option<String>,
+    pub disposition: Option<Disposition>,
+    /// Where restocked or refurbished units went
+    pub target_location_id: Option<Uuid>,
+    /// Supplier that return-to-supplier units were shipped to
+    pub supplier_id: Option<Uuid>,
+    pub disposition_note: Option<String>,
+    pub inspected_by: Option<Uuid>,
+    pub inspected_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReturnOrder {
+    pub id: Uuid,
+    pub tenant_id: Uuid,
+    /// `RMA-` number quoted on movements and to the customer
+    pub return_number: String,
+    pub customer_id: Uuid,
+    /// Original sales order, when known
+    pub order_id: Option<Uuid>,
+    pub status: ReturnStatus,
+    /// Set on receipt
+    pub quarantine_location_id: Option<Uuid>,
+    pub notes: Option<String>,
+    pub lines: Vec<ReturnOrderLine>,
+    pub created_by: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub received_by: Option<Uuid>,
+    pub received_at: Option<DateTime<Utc>>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateReturnLineRequest {
+    pub product_id: Uuid,
+    pub quantity: i32,
+    pub reason: ReturnReason,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateReturnOrderRequest {
+    pub customer_id: Uuid,
+    pub order_id: Option<Uuid>,
+    pub notes: Option<String>,
+    pub lines: Vec<CreateReturnLineRequest>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReceiveReturnRequest {
+    /// Must be a location of a quarantine location type
+    pub quarantine_location_id: Uuid,
+}
+
+/// The decision for one line
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LineInspection {
+    pub line_id: Uuid,
+    pub disposition: Disposition,
+    /// Required for `restock` (sellable location) and `refurbish` (quarantine location)
+    pub target_location_id: Option<Uuid>,
+    /// Required for `return_to_supplier`
+    pub supplier_id: Option<Uuid>,
+    /// Required for `scrap`: the write-off reason
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InspectReturnRequest {
+    pub lines: Vec<LineInspection>,
+}
+
+/// Returned against shipped units of one product over a period
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProductReturnRate {
+    pub product_id: Uuid,
+    pub returned_quantity: i64,
+    pub shipped_quantity: i64,
+    /// Returned divided by shipped units; 0 when nothing was shipped
+    pub return_rate: f64,
+    pub returned_by_reason: HashMap<String, i64>,
+}
+
+impl ReturnOrder {
+    /// A new order in `requested`
+    pub fn new(
+        tenant_id: Uuid,
+        request: CreateReturnOrderRequest,
+        created_by: Uuid,
+        now: DateTime<Utc>,
+    ) -> Result<Self> {
+        validate_new_lines(&request.lines)?;
+
+        let id = Uuid::new_v4();
+        Ok(Self {
+            id,
+            tenant_id,
+            return_number: return_number(id, now),
+            customer_id: request.customer_id,
+            order_id: request.order_id,
+            status: ReturnStatus::Requested,
+            quarantine_location_id: None,
+            notes: request.notes.filter(|notes| !notes.trim().is_empty()),
+            lines: request
+                .lines
+                .into_iter()
+                .map(|line| ReturnOrderLine {
+                    id: Uuid::new_v4(),
+                    product_id: line.product_id,
+                    quantity: line.quantity,
+                    reason: line.reason,
+                    note: line.note.filter(|note| !note.trim().is_empty()),
+                    disposition: None,
+                    target_location_id: None,
+                    supplier_id: None,
+                    disposition_note: None,
+                    inspected_by: None,
+                    inspected_at: None,
+                })
+                .collect(),
+            created_by,
+            created_at: now,
+            updated_at: now,
+            received_by: None,
+            received_at: None,
+            completed_at: None,
+        })
+    }
+
+    fn transition(&mut self, next: ReturnStatus, now: DateTime<Utc>) -> Result<()> {
+        if !self.status.can_transition_to(next) {
+            return Err(MasterDataError::InvalidReturnTransition {
+                return_number: self.return_number.clone(),
+                from: self.status.as_str().to_string(),
+                to: next.as_str().to_string(),
+            });
+        }
+        self.status = next;
+        self.updated_at = now;
+        Ok(())
+    }
+
+    /// Book the returned units into quarantine; returns the receipt movements
+    pub fn receive(
+        &mut self,
+        quarantine_location_id: Uuid,
+        received_by: Uuid,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<InventoryMovement>> {
+        self.transition(ReturnStatus::Received, now)?;
+        self.quarantine_location_id = Some(quarantine_location_id);
+        self.received_by = Some(received_by);
+        self.received_at = Some(now);
+
+        Ok(self
+            .lines
+            .iter()
+            .map(|line| {
+                let mut movement = self.movement(line, quarantine_location_id, "return", line.quantity, received_by, now);
+                movement.reason = Some(line.reason.as_str().to_string());
+                movement
+            })
+            .collect())
+    }
+
+    pub fn cancel(&mut self, now: DateTime<Utc>) -> Result<()> {
+        self.transition(ReturnStatus::Cancelled, now)
+    }
+
+    /// Record dispositions and return the movements that carry them out.
+    ///
+    /// Location types are checked by the caller; this only checks that the
+    /// fields each disposition needs are present.
+    pub fn inspect(
+        &mut self,
+        request: &InspectReturnRequest,
+        inspected_by: Uuid,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<InventoryMovement>> {
+        if self.status != ReturnStatus::Received {
+            return Err(MasterDataError::InvalidReturnTransition {
+                return_number: self.return_number.clone(),
+                from: self.status.as_str().to_string(),
+                to: ReturnStatus::Inspected.as_str().to_string(),
+            });
+        }
+        if request.lines.is_empty() {
+            return Err(validation("lines", "At least one line inspection is required"));
+        }
+        let quarantine = self
+            .quarantine_location_id
+            .ok_or_else(|| MasterDataError::Internal {
+                message: format!("Received return {} has no quarantine location", self.return_number),
+            })?;
+
+        let mut seen = HashSet::new();
+        let mut movements = Vec::new();
+        for inspection in &request.lines {
+            if !seen.insert(inspection.line_id) {
+                return Err(validation("lines", &format!("Line {} is inspected twice", inspection.line_id)));
+            }
+            let line = self
+                .lines
+                .iter()
+                .find(|line| line.id == inspection.line_id)
+                .ok_or_else(|| validation("line_id", &format!("Line {} is not on this return", inspection.line_id)))?;
+            if line.disposition.is_some() {
+                return Err(validation("line_id", &format!("Line {} was already inspected", line.id)));
+            }
+            movements.extend(self.disposition_movements(line, inspection, quarantine, inspected_by, now)?);
+        }
+
+        for inspection in &request.lines {
+            if let Some(line) = self.lines.iter_mut().find(|line| line.id == inspection.line_id) {
+                line.disposition = Some(inspection.disposition);
+                line.target_location_id = inspection.target_location_id;
+                line.supplier_id = inspection.supplier_id;
+                line.disposition_note = inspection.note.clone();
+                line.inspected_by = Some(inspected_by);
+                line.inspected_at = Some(now);
+            }
+        }
+
+        self.updated_at = now;
+        if self.lines.iter().all(|line| line.disposition.is_some()) {
+            self.transition(ReturnStatus::Inspected, now)?;
+            self.completed_at = Some(now);
+        }
+        Ok(movements)
+    }
+
+    fn disposition_movements(
+        &self,
+        line: &ReturnOrderLine,
+        inspection: &LineInspection,
+        quarantine: Uuid,
+        operator: Uuid,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<InventoryMovement>> {
+        let out_of_quarantine = |movement_type: &str| self.movement(line, quarantine, movement_type, -line.quantity, operator, now);
+
+        match inspection.disposition {
+            Disposition::Restock | Disposition::Refurbish => {
+                let target = inspection
+                    .target_location_id
+                    .ok_or_else(|| validation("target_location_id", "Restock and refurbish need a target location"))?;
+                if target == quarantine {
+                    return Err(validation("target_location_id", "Target location must differ from the quarantine location"));
+                }
+                Ok(vec![
+                    out_of_quarantine("transfer"),
+                    self.movement(line, target, "transfer", line.quantity, operator, now),
+                ])
+            }
+            Disposition::Scrap => {
+                let reason = inspection
+                    .note
+                    .as_deref()
+                    .map(str::trim)
+                    .filter(|note| !note.is_empty())
+                    .ok_or_else(|| validation("note", "Scrapping needs a write-off reason"))?;
+                let mut movement = out_of_quarantine("damage");
+                movement.reason = Some(reason.to_string());
+                Ok(vec![movement])
+            }
+            Disposition::ReturnToSupplier => {
+                let supplier = inspection
+                    .supplier_id
+                    .ok_or_else(|| validation("supplier_id", "Return to supplier needs a supplier"))?;
+                let mut movement = out_of_quarantine("shipment");
+                movement.reference_document = Some(format!("supplier:{}", supplier));
+                movement.reason = Some(Disposition::ReturnToSupplier.as_str().to_string());
+                Ok(vec![movement])
+            }
+        }
+    }
+
+    fn movement(
+        &self,
+        line: &ReturnOrderLine,
+        location_id: Uuid,
+        movement_type: &str,
+        quantity: i32,
+        operator: Uuid,
+        now: DateTime<Utc>,
+    ) -> InventoryMovement {
+        InventoryMovement {
+            id: Some(Uuid::new_v4()),
+            product_id: Some(line.product_id),
+            location_id: Some(location_id),
+            movement_type: Some(movement_type.to_string()),
+            quantity: Some(quantity),
+            unit_cost: None,
+            reference_document: None,
+            reference_number: Some(self.return_number.clone()),
+            reason: None,
+            batch_number: None,
+            serial_numbers: None,
+            expiry_date: None,
+            operator_id: Some(operator),
+            operator_name: None,
+            created_at: Some(now),
+            effective_date: Some(now),
+            audit_trail: None,
+        }
+    }
+}
+
+/// `RMA-YYYYMMDD-` plus the first eight hex digits of the order id
+fn return_number(id: Uuid, now: DateTime<Utc>) -> String {
+    format!("RMA-{}-{}", now.format("%Y%m%d"), &id.simple().to_string()[..8].to_uppercase())
+}
+
+fn validate_new_lines(lines: &[CreateReturnLineRequest]) -> Result<()> {
+    if lines.is_empty() {
+        return Err(validation("lines", "A return needs at least one line"));
+    }
+    if let Some(line) = lines.iter().find(|line| line.quantity <= 0) {
+        return Err(validation(
+            "quantity",
+            &format!("Returned quantity of product {} must be positive", line.product_id),
+        ));
+    }
+    Ok(())
+}
+
+fn validation(field: &str, message: &str) -> MasterDataError {
+    MasterDataError::ValidationError {
+        field: field.to_string(),
+        message: message.to_string(),
+    }
+}
+
+/// Combine returned units (by product and reason) with shipped units into
+/// per-product return rates, highest rate first
+pub fn compute_return_rates(
+    returned: &[(Uuid, ReturnReason, i64)],
+    shipped: &HashMap<Uuid, i64>,
+) -> Vec<ProductReturnRate> {
+    let mut by_product: HashMap<Uuid, ProductReturnRate> = HashMap::new();
+    for &(product_id, reason, quantity) in returned {
+        let rate = by_product.entry(product_id).or_insert_with(|| ProductReturnRate {
+            product_id,
+            returned_quantity: 0,
+            shipped_quantity: shipped.get(&product_id).copied().unwrap_or(0),
+            return_rate: 0.0,
+            returned_by_reason: HashMap::new(),
+        });
+        rate.returned_quantity += quantity;
+        *rate.returned_by_reason.entry(reason.as_str().to_string()).or_insert(0) += quantity;
+    }
+
+    let mut rates: Vec<ProductReturnRate> = by_product
+        .into_values()
+        .map(|mut rate| {
+            if rate.shipped_quantity > 0 {
+                rate.return_rate = rate.returned_quantity as f64 / rate.shipped_quantity as f64;
+            }
+            rate
+        })
+        .collect();
+    rates.sort_by(|a, b| b.return_rate.total_cmp(&a.return_rate).then(a.product_id.cmp(&b.product_id)));
+    rates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn order(lines: &[(Uuid, i32)]) -> ReturnOrder {
+        ReturnOrder::new(
+            Uuid::new_v4(),
+            CreateReturnOrderRequest {
+                customer_id: Uuid::new_v4(),
+                order_id: None,
+                notes: None,
+                lines: lines
+                    .iter()
+                    .map(|&(product_id, quantity)| CreateReturnLineRequest {
+                        product_id,
+                        quantity,
+                        reason: ReturnReason::Defective,
+                        note: None,
+                    })
+                    .collect(),
+            },
+            Uuid::new_v4(),
+            Utc::now(),
+        )
+        .unwrap()
+    }
+
+    fn received(lines: &[(Uuid, i32)], quarantine: Uuid) -> ReturnOrder {
+        let mut order = order(lines);
+        order.receive(quarantine, Uuid::new_v4(), Utc::now()).unwrap();
+        order
+    }
+
+    fn inspection(line_id: Uuid, disposition: Disposition) -> LineInspection {
+        LineInspection {
+            line_id,
+            disposition,
+            target_location_id: None,
+            supplier_id: None,
+            note: None,
+        }
+    }
+
+    fn inspect(order: &mut ReturnOrder, inspections: Vec<LineInspection>) -> Result<Vec<InventoryMovement>> {
+        order.inspect(&InspectReturnRequest { lines: inspections }, Uuid::new_v4(), Utc::now())
+    }
+
+    fn booked(movements: &[InventoryMovement]) -> Vec<(Uuid, &str, i32)> {
+        movements
+            .iter()
+            .map(|m| (m.location_id.unwrap(), m.movement_type.as_deref().unwrap(), m.quantity.unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn test_new_return_validates_lines() {
+        let request = |lines| CreateReturnOrderRequest { customer_id: Uuid::new_v4(), order_id: None, notes: None, lines };
+        assert!(ReturnOrder::new(Uuid::new_v4(), request(vec![]), Uuid::new_v4(), Utc::now()).is_err());
+
+        let zero = CreateReturnLineRequest { product_id: Uuid::new_v4(), quantity: 0, reason: ReturnReason::Other, note: None };
+        assert!(ReturnOrder::new(Uuid::new_v4(), request(vec![zero]), Uuid::new_v4(), Utc::now()).is_err());
+
+        let order = order(&[(Uuid::new_v4(), 2)]);
+        assert_eq!(order.status, ReturnStatus::Requested);
+        assert!(order.return_number.starts_with("RMA-"));
+    }
+
+    #[test]
+    fn test_receive_books_into_quarantine() {
+        let product = Uuid::new_v4();
+        let quarantine = Uuid::new_v4();
+        let mut order = order(&[(product, 3)]);
+
+        let movements = order.receive(quarantine, Uuid::new_v4(), Utc::now()).unwrap();
+
+        assert_eq!(order.status, ReturnStatus::Received);
+        assert_eq!(booked(&movements), vec![(quarantine, "return", 3)]);
+        assert_eq!(movements[0].reason.as_deref(), Some("defective"));
+        assert_eq!(movements[0].reference_number.as_deref(), Some(order.return_number.as_str()));
+    }
+
+    #[test]
+    fn test_state_machine_rejects_invalid_transitions() {
+        let mut order = order(&[(Uuid::new_v4(), 1)]);
+        let line_id = order.lines[0].id;
+
+        // Inspecting before receipt
+        assert!(matches!(
+            inspect(&mut order, vec![inspection(line_id, Disposition::Scrap)]),
+            Err(MasterDataError::InvalidReturnTransition { .. })
+        ));
+
+        order.receive(Uuid::new_v4(), Uuid::new_v4(), Utc::now()).unwrap();
+        assert!(order.receive(Uuid::new_v4(), Uuid::new_v4(), Utc::now()).is_err());
+        assert!(matches!(order.cancel(Utc::now()), Err(MasterDataError::InvalidReturnTransition { .. })));
+
+        let mut cancelled = self::order(&[(Uuid::new_v4(), 1)]);
+        cancelled.cancel(Utc::now()).unwrap();
+        assert!(cancelled.receive(Uuid::new_v4(), Uuid::new_v4(), Utc::now()).is_err());
+    }
+
+    #[test]
+    fn test_restock_moves_to_sellable_location() {
+        let quarantine = Uuid::new_v4();
+        let shelf = Uuid::new_v4();
+        let mut order = received(&[(Uuid::new_v4(), 4)], quarantine);
+        let mut restock = inspection(order.lines[0].id, Disposition::Restock);
+        restock.target_location_id = Some(shelf);
+
+        let movements = inspect(&mut order, vec![restock]).unwrap();
+
+        assert_eq!(booked(&movements), vec![(quarantine, "transfer", -4), (shelf, "transfer", 4)]);
+        assert_eq!(order.status, ReturnStatus::Inspected);
+        assert!(order.completed_at.is_some());
+    }
+
+    #[test]
+    fn test_restock_needs_a_target_other_than_quarantine() {
+        let quarantine = Uuid::new_v4();
+        let mut order = received(&[(Uuid::new_v4(), 1)], quarantine);
+        let line_id = order.lines[0].id;
+
+        assert!(inspect(&mut order, vec![inspection(line_id, Disposition::Restock)]).is_err());
+
+        let mut same = inspection(line_id, Disposition::Restock);
+        same.target_location_id = Some(quarantine);
+        assert!(inspect(&mut order, vec![same]).is_err());
+        assert_eq!(order.lines[0].disposition, None);
+    }
+
+    #[test]
+    fn test_refurbish_moves_to_work_area() {
+        let quarantine = Uuid::new_v4();
+        let workshop = Uuid::new_v4();
+        let mut order = received(&[(Uuid::new_v4(), 2)], quarantine);
+        let mut refurbish = inspection(order.lines[0].id, Disposition::Refurbish);
+        refurbish.target_location_id = Some(workshop);
+
+        let movements = inspect(&mut order, vec![refurbish]).unwrap();
+
+        assert_eq!(booked(&movements), vec![(quarantine, "transfer", -2), (workshop, "transfer", 2)]);
+        assert_eq!(order.lines[0].target_location_id, Some(workshop));
+    }
+
+    #[test]
+    fn test_scrap_writes_off_with_reason() {
+        let quarantine = Uuid::new_v4();
+        let mut order = received(&[(Uuid::new_v4(), 5)], quarantine);
+        let line_id = order.lines[0].id;
+
+        assert!(inspect(&mut order, vec![inspection(line_id, Disposition::Scrap)]).is_err());
+
+        let mut scrap = inspection(line_id, Disposition::Scrap);
+        scrap.note = Some("Water damage".to_string());
+        let movements = inspect(&mut order, vec![scrap]).unwrap();
+
+        assert_eq!(booked(&movements), vec![(quarantine, "damage", -5)]);
+        assert_eq!(movements[0].reason.as_deref(), Some("Water damage"));
+    }
+
+    #[test]
+    fn test_return_to_supplier_ships_out() {
+        let quarantine = Uuid::new_v4();
+        let supplier = Uuid::new_v4();
+        let mut order = received(&[(Uuid::new_v4(), 1)], quarantine);
+        let line_id = order.lines[0].id;
+
+        assert!(inspect(&mut order, vec![inspection(line_id, Disposition::ReturnToSupplier)]).is_err());
+
+        let mut rts = inspection(line_id, Disposition::ReturnToSupplier);
+        rts.supplier_id = Some(supplier);
+        let movements = inspect(&mut order, vec![rts]).unwrap();
+
+        assert_eq!(booked(&movements), vec![(quarantine, "shipment", -1)]);
+        assert_eq!(movements[0].reference_document, Some(format!("supplier:{}", supplier)));
+        assert_eq!(order.lines[0].supplier_id, Some(supplier));
+    }
+
+    #[test]
+    fn test_partial_inspection_keeps_order_received() {
+        let mut order = received(&[(Uuid::new_v4(), 1), (Uuid::new_v4(), 1)], Uuid::new_v4());
+        let (first, second) = (order.lines[0].id, order.lines[1].id);
+        let scrap = |line_id| LineInspection { note: Some("Broken".to_string()), ..inspection(line_id, Disposition::Scrap) };
+
+        inspect(&mut order, vec![scrap(first)]).unwrap();
+        assert_eq!(order.status, ReturnStatus::Received);
+
+        // Already inspected lines cannot be decided again
+        assert!(inspect(&mut order, vec![scrap(first)]).is_err());
+
+        inspect(&mut order, vec![scrap(second)]).unwrap();
+        assert_eq!(order.status, ReturnStatus::Inspected);
+    }
+
+    #[test]
+    fn test_compute_return_rates() {
+        let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+        let returned = vec![
+            (a, ReturnReason::Defective, 2),
+            (a, ReturnReason::WrongItem, 1),
+            (b, ReturnReason::Defective, 1),
+        ];
+        let shipped = HashMap::from([(a, 10), (b, 100)]);
+
+        let rates = compute_return_rates(&returned, &shipped);
+
+        assert_eq!(rates[0].product_id, a);
+        assert_eq!(rates[0].returned_quantity, 3);
+        assert!((rates[0].return_rate - 0.3).abs() < 1e-9);
+        assert_eq!(rates[0].returned_by_reason["defective"], 2);
+        assert!((rates[1].return_rate - 0.01).abs() < 1e-9);
+
+        // Returns without recorded shipments have no rate rather than dividing by zero
+        let rates = compute_return_rates(&[(a, ReturnReason::Other, 1)], &HashMap::new());
+        assert_eq!(rates[0].return_rate, 0.0);
+    }
+}
@@ -0,0 +1,560 @@
+//! # Forecast Scenarios
+//!
+//! What-if projections for promotions: the normal demand forecast is run for
+//! each product/location, one or more [`DemandUplift`]s are applied on top,
+//! and stock is projected day by day against on-hand quantity and incoming
+//! supply (open purchase orders and in-transit transfers).
+//!
+//! For each item the result names the first projected stockout, the extra
+//! quantity needed to avoid every stockout in the range and the latest date
+//! an order can be placed to arrive in time given the item's lead time.
+//!
+//! ## Uplift ramp
+//!
+//! An uplift with `ramp_days = n` reaches its full effect on day `n` of its
+//! window and grows linearly before that: on day `i` (1-based) the fraction
+//! applied is `min(i / n, 1)`. A multiplier `m` at fraction `f` scales demand
+//! by `1 + (m - 1) * f`; additional units `u` add `u * f` per day. Where
+//! uplifts overlap, multipliers are applied first, then additional units.
+//!
+//! The functions here are pure; the forecast engine, stock figures and
+//! storage are wired in [`ForecastScenarioService`](super::service::ForecastScenarioService).
+
+use crate::error::{MasterDataError, Result};
+use crate::inventory::explanation::LeadTimeSource;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Longest projection accepted, in days from today
+pub const MAX_SCENARIO_HORIZON_DAYS: i64 = 366;
+
+/// Most product/location pairs in one scenario; each runs its own forecast
+pub const MAX_SCENARIO_ITEMS: usize = 200;
+
+/// How an uplift changes the daily demand
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum UpliftKind {
+    /// Baseline demand times this factor, e.g. 3.0 for a 3x spike
+    Multiplier(f64),
+    /// Extra units per day on top of the baseline
+    AdditionalUnits(f64),
+}
+
+/// A demand change over a date window
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DemandUplift {
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub kind: UpliftKind,
+    /// Days until the full effect is reached; 0 applies it from the first day
+    #[serde(default)]
+    pub ramp_days: u32,
+    /// Products the uplift applies to; all products of the scenario when empty
+    #[serde(default)]
+    pub product_ids: Vec<Uuid>,
+}
+
+impl DemandUplift {
+    /// Share of the full effect applied on `date`; 0 outside the window
+    pub fn fraction_on(&self, date: NaiveDate) -> f64 {
+        if date < self.start_date || date > self.end_date {
+            return 0.0;
+        }
+        if self.ramp_days == 0 {
+            return 1.0;
+        }
+        let day = (date - self.start_date).num_days() + 1;
+        (day as f64 / self.ramp_days as f64).min(1.0)
+    }
+
+    fn applies_to(&self, product_id: Uuid) -> bool {
+        self.product_ids.is_empty() || self.product_ids.contains(&product_id)
+    }
+}
+
+/// Demand of one product on one day after every applicable uplift
+pub fn apply_uplifts(product_id: Uuid, date: NaiveDate, baseline: f64, uplifts: &[DemandUplift]) -> f64 {
+    let applicable: Vec<(&DemandUplift, f64)> = uplifts
+        .iter()
+        .filter(|uplift| uplift.applies_to(product_id))
+        .map(|uplift| (uplift, uplift.fraction_on(date)))
+        .filter(|(_, fraction)| *fraction > 0.0)
+        .collect();
+
+    let mut demand = baseline;
+    for (uplift, fraction) in &applicable {
+        if let UpliftKind::Multiplier(factor) = uplift.kind {
+            demand *= 1.0 + (factor - 1.0) * fraction;
+        }
+    }
+    for (uplift, fraction) in &applicable {
+        if let UpliftKind::AdditionalUnits(units) = uplift.kind {
+            demand += units * fraction;
+        }
+    }
+    demand.max(0.0)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SupplySource {
+    PurchaseOrder,
+    Transfer,
+}
+
+/// Stock expected to arrive at the location
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IncomingSupply {
+    pub source: SupplySource,
+    pub reference_id: Uuid,
+    pub product_id: Uuid,
+    pub location_id: Uuid,
+    pub quantity: f64,
+    /// Arrival date; supply without one is counted as available from the first projected day
+    pub expected_date: Option<NaiveDate>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ScenarioItem {
+    pub product_id: Uuid,
+    pub location_id: Uuid,
+}
+
+/// `POST /inventory/forecast/scenario`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ForecastScenarioRequest {
+    pub name: String,
+    pub items: Vec<ScenarioItem>,
+    /// First day reported; stock is still projected from tomorrow so earlier demand is accounted for
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    #[serde(default)]
+    pub uplifts: Vec<DemandUplift>,
+    /// Store the scenario for later comparison with actual demand
+    #[serde(default)]
+    pub save: bool,
+}
+
+impl ForecastScenarioRequest {
+    pub fn validate(&self, today: NaiveDate) -> Result<()> {
+        if self.name.trim().is_empty() {
+            return Err(validation("name", "Scenario name is required"));
+        }
+        if self.items.is_empty() {
+            return Err(validation("items", "At least one product/location is required"));
+        }
+        if self.items.len() > MAX_SCENARIO_ITEMS {
+            return Err(validation(
+                "items",
+                &format!("A scenario may cover at most {} product/locations", MAX_SCENARIO_ITEMS),
+            ));
+        }
+        if self.start_date > self.end_date {
+            return Err(validation("start_date", "Start date must not be after end date"));
+        }
+        if self.end_date <= today {
+            return Err(validation("end_date", "End date must be in the future"));
+        }
+        if (self.end_date - today).num_days() > MAX_SCENARIO_HORIZON_DAYS {
+            return Err(validation(
+                "end_date",
+                &format!("Scenarios may look at most {} days ahead", MAX_SCENARIO_HORIZON_DAYS),
+            ));
+        }
+        for uplift in &self.uplifts {
+            if uplift.start_date > uplift.end_date {
+                return Err(validation("uplifts", "Uplift start date must not be after its end date"));
+            }
+            let valid = match uplift.kind {
+                UpliftKind::Multiplier(factor) => factor.is_finite() && factor >= 0.0,
+                UpliftKind::AdditionalUnits(units) => units.is_finite(),
+            };
+            if !valid {
+                return Err(validation("uplifts", "Uplift values must be finite and multipliers not negative"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Days from today to the end date, the forecast horizon to request
+    pub fn horizon_days(&self, today: NaiveDate) -> i32 {
+        (self.end_date - today).num_days() as i32
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProjectedDay {
+    pub date: NaiveDate,
+    pub baseline_demand: f64,
+    pub scenario_demand: f64,
+    pub incoming: f64,
+    /// Stock at the end of the day; negative is unmet demand
+    pub projected_stock: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScenarioItemResult {
+    pub product_id: Uuid,
+    pub location_id: Uuid,
+    pub opening_stock: f64,
+    pub lead_time_days: f64,
+    pub lead_time_source: LeadTimeSource,
+    /// False when there was no demand history to forecast from; baseline demand is then zero
+    pub forecast_available: bool,
+    pub baseline_demand_total: f64,
+    pub scenario_demand_total: f64,
+    pub incoming_total: f64,
+    /// First day the scenario runs out of stock
+    pub stockout_date: Option<NaiveDate>,
+    /// First day the plain forecast runs out of stock, for comparison
+    pub baseline_stockout_date: Option<NaiveDate>,
+    /// Units that must arrive before `stockout_date` to avoid every stockout in the range
+    pub additional_quantity_needed: f64,
+    /// Latest day to order so the additional quantity arrives in time
+    pub order_by_date: Option<NaiveDate>,
+    /// The order-by date has already passed
+    pub order_overdue: bool,
+    /// Projection from `start_date` to `end_date`
+    pub days: Vec<ProjectedDay>,
+}
+
+/// A scenario run, as returned and stored
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ForecastScenario {
+    pub id: Uuid,
+    pub tenant_id: Uuid,
+    pub name: String,
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub uplifts: Vec<DemandUplift>,
+    pub items: Vec<ScenarioItemResult>,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Per-item inputs gathered by the service
+#[derive(Debug, Clone)]
+pub struct ItemInputs {
+    pub item: ScenarioItem,
+    pub opening_stock: f64,
+    /// Baseline forecast from tomorrow, one value per day
+    pub daily_forecast: Vec<f64>,
+    pub forecast_available: bool,
+    pub supply: Vec<IncomingSupply>,
+    pub lead_time_days: f64,
+    pub lead_time_source: LeadTimeSource,
+}
+
+/// Project one item from tomorrow to `end_date` and summarize it
+pub fn project_item(
+    inputs: &ItemInputs,
+    uplifts: &[DemandUplift],
+    today: NaiveDate,
+    start_date: NaiveDate,
+    end_date: NaiveDate,
+) -> ScenarioItemResult {
+    let first_day = today + Duration::days(1);
+    let mut incoming_by_day: HashMap<NaiveDate, f64> = HashMap::new();
+    for supply in &inputs.supply {
+        let arrival = supply.expected_date.map_or(first_day, |date| date.max(first_day));
+        if arrival <= end_date {
+            *incoming_by_day.entry(arrival).or_insert(0.0) += supply.quantity;
+        }
+    }
+
+    let mut stock = inputs.opening_stock;
+    let mut baseline_stock = inputs.opening_stock;
+    let mut lowest = 0.0_f64;
+    let mut stockout_date = None;
+    let mut baseline_stockout_date = None;
+    let mut days = Vec::new();
+    let (mut baseline_total, mut scenario_total, mut incoming_total) = (0.0, 0.0, 0.0);
+
+    let mut date = first_day;
+    let mut index = 0;
+    while date <= end_date {
+        let baseline = inputs.daily_forecast.get(index).copied().unwrap_or(0.0).max(0.0);
+        let demand = apply_uplifts(inputs.item.product_id, date, baseline, uplifts);
+        let incoming = incoming_by_day.get(&date).copied().unwrap_or(0.0);
+
+        stock += incoming - demand;
+        baseline_stock += incoming - baseline;
+        if stock < 0.0 && stockout_date.is_none() {
+            stockout_date = Some(date);
+        }
+        if baseline_stock < 0.0 && baseline_stockout_date.is_none() {
+            baseline_stockout_date = Some(date);
+        }
+        lowest = lowest.min(stock);
+
+        if date >= start_date {
+            days.push(ProjectedDay {
+                date,
+                baseline_demand: baseline,
+                scenario_demand: demand,
+                incoming,
+                projected_stock: stock,
+            });
+        }
+        baseline_total += baseline;
+        scenario_total += demand;
+        incoming_total += incoming;
+
+        date += Duration::days(1);
+        index += 1;
+    }
+
+    let order_by_date = stockout_date.map(|stockout| order_by(stockout, inputs.lead_time_days));
+    ScenarioItemResult {
+        product_id: inputs.item.product_id,
+        location_id: inputs.item.location_id,
+        opening_stock: inputs.opening_stock,
+        lead_time_days: inputs.lead_time_days,
+        lead_time_source: inputs.lead_time_source,
+        forecast_available: inputs.forecast_available,
+        baseline_demand_total: baseline_total,
+        scenario_demand_total: scenario_total,
+        incoming_total,
+        stockout_date,
+        baseline_stockout_date,
+        additional_quantity_needed: (-lowest).ceil(),
+        order_by_date,
+        order_overdue: order_by_date.is_some_and(|date| date < today),
+        days,
+    }
+}
+
+/// Latest order date for goods to arrive by the start of `needed_on`
+pub fn order_by(needed_on: NaiveDate, lead_time_days: f64) -> NaiveDate {
+    needed_on - Duration::days(lead_time_days.ceil().max(0.0) as i64)
+}
+
+/// Projected against actual demand for the days of a saved scenario that have passed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScenarioItemComparison {
+    pub product_id: Uuid,
+    pub location_id: Uuid,
+    pub days_compared: usize,
+    pub projected_demand: f64,
+    pub actual_demand: f64,
+    /// (actual - projected) / projected; `None` when nothing was projected
+    pub demand_variance_ratio: Option<f64>,
+    pub projected_stockout_date: Option<NaiveDate>,
+}
+
+/// Compare a stored item result with actual daily demand up to and including `until`
+pub fn compare_with_actuals(
+    result: &ScenarioItemResult,
+    actual_daily_demand: &HashMap<NaiveDate, f64>,
+    until: NaiveDate,
+) -> ScenarioItemComparison {
+    let elapsed: Vec<&ProjectedDay> = result.days.iter().filter(|day| day.date <= until).collect();
+    let projected: f64 = elapsed.iter().map(|day| day.scenario_demand).sum();
+    let actual: f64 = elapsed
+        .iter()
+        .map(|day| actual_daily_demand.get(&day.date).copied().unwrap_or(0.0))
+        .sum();
+
+    ScenarioItemComparison {
+        product_id: result.product_id,
+        location_id: result.location_id,
+        days_compared: elapsed.len(),
+        projected_demand: projected,
+        actual_demand: actual,
+        demand_variance_ratio: (projected > 0.0).then(|| (actual - projected) / projected),
+        projected_stockout_date: result.stockout_date,
+    }
+}
+
+/// A saved scenario compared with what actually happened so far
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ForecastScenarioComparison {
+    pub scenario_id: Uuid,
+    pub name: String,
+    /// Last day included; days after it have not happened yet
+    pub compared_through: NaiveDate,
+    pub items: Vec<ScenarioItemComparison>,
+}
+
+fn validation(field: &str, message: &str) -> MasterDataError {
+    MasterDataError::ValidationError {
+        field: field.to_string(),
+        message: message.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 11, day).unwrap()
+    }
+
+    fn inputs(opening_stock: f64, daily: f64, days: usize, lead_time_days: f64) -> ItemInputs {
+        ItemInputs {
+            item: ScenarioItem {
+                product_id: Uuid::new_v4(),
+                location_id: Uuid::new_v4(),
+            },
+            opening_stock,
+            daily_forecast: vec![daily; days],
+            forecast_available: true,
+            supply: Vec::new(),
+            lead_time_days,
+            lead_time_source: LeadTimeSource::SupplierCatalog,
+        }
+    }
+
+    fn uplift(start: u32, end: u32, kind: UpliftKind, ramp_days: u32) -> DemandUplift {
+        DemandUplift {
+            start_date: date(start),
+            end_date: date(end),
+            kind,
+            ramp_days,
+            product_ids: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_ramp_reaches_full_effect_on_ramp_day() {
+        let ramped = uplift(10, 20, UpliftKind::Multiplier(3.0), 4);
+        let product = Uuid::new_v4();
+
+        assert_eq!(ramped.fraction_on(date(9)), 0.0);
+        assert_eq!(ramped.fraction_on(date(10)), 0.25);
+        assert_eq!(ramped.fraction_on(date(11)), 0.5);
+        assert_eq!(ramped.fraction_on(date(13)), 1.0);
+        assert_eq!(ramped.fraction_on(date(20)), 1.0);
+        assert_eq!(ramped.fraction_on(date(21)), 0.0);
+
+        // 1 + (3 - 1) * 0.5 = 2x on the second day
+        assert_eq!(apply_uplifts(product, date(11), 10.0, std::slice::from_ref(&ramped)), 20.0);
+        assert_eq!(apply_uplifts(product, date(15), 10.0, &[ramped]), 30.0);
+    }
+
+    #[test]
+    fn test_multipliers_apply_before_additional_units() {
+        let product = Uuid::new_v4();
+        let uplifts = [
+            uplift(1, 30, UpliftKind::AdditionalUnits(5.0), 0),
+            uplift(1, 30, UpliftKind::Multiplier(2.0), 0),
+        ];
+        assert_eq!(apply_uplifts(product, date(5), 10.0, &uplifts), 25.0);
+
+        let other_product = DemandUplift {
+            product_ids: vec![Uuid::new_v4()],
+            ..uplift(1, 30, UpliftKind::Multiplier(2.0), 0)
+        };
+        assert_eq!(apply_uplifts(product, date(5), 10.0, &[other_product]), 10.0);
+    }
+
+    #[test]
+    fn test_order_by_date_uses_lead_time() {
+        // 100 on hand, 10 a day, tripled from the 6th: runs out on the 8th
+        let today = date(1);
+        let item = inputs(100.0, 10.0, 14, 5.0);
+        let result = project_item(&item, &[uplift(6, 14, UpliftKind::Multiplier(3.0), 0)], today, date(2), date(14));
+
+        // Days 2-5: 40 sold, 60 left; 30 a day from the 6th: 30, 0, -30
+        assert_eq!(result.stockout_date, Some(date(8)));
+        assert_eq!(result.order_by_date, Some(date(3)));
+        assert!(!result.order_overdue);
+        // Days 8-14 at 30 a day, starting from 0
+        assert_eq!(result.additional_quantity_needed, 210.0);
+        // Without the promotion the stock would have lasted until the 11th
+        assert_eq!(result.baseline_stockout_date, Some(date(12)));
+
+        // Partial lead time days round up; the order-by date may fall in an earlier month
+        assert_eq!(order_by(date(8), 9.5), NaiveDate::from_ymd_opt(2026, 10, 29).unwrap());
+    }
+
+    #[test]
+    fn test_open_purchase_orders_cover_the_spike() {
+        let today = date(1);
+        let mut item = inputs(50.0, 10.0, 14, 7.0);
+        item.supply = vec![
+            IncomingSupply {
+                source: SupplySource::PurchaseOrder,
+                reference_id: Uuid::new_v4(),
+                product_id: item.item.product_id,
+                location_id: item.item.location_id,
+                quantity: 200.0,
+                expected_date: Some(date(5)),
+            },
+            IncomingSupply {
+                source: SupplySource::Transfer,
+                reference_id: Uuid::new_v4(),
+                product_id: item.item.product_id,
+                location_id: item.item.location_id,
+                quantity: 20.0,
+                expected_date: None,
+            },
+        ];
+
+        let result = project_item(&item, &[uplift(6, 12, UpliftKind::Multiplier(3.0), 0)], today, date(2), date(12));
+
+        // 50 + 220 incoming against 40 + 7 * 30 = 250 demand
+        assert_eq!(result.incoming_total, 220.0);
+        assert_eq!(result.scenario_demand_total, 250.0);
+        assert_eq!(result.stockout_date, None);
+        assert_eq!(result.order_by_date, None);
+        assert_eq!(result.additional_quantity_needed, 0.0);
+        assert_eq!(result.days.last().unwrap().projected_stock, 20.0);
+        // The transfer without a date arrives on the first projected day
+        assert_eq!(result.days[0].incoming, 20.0);
+    }
+
+    #[test]
+    fn test_overdue_order_is_flagged() {
+        let today = date(1);
+        let item = inputs(15.0, 10.0, 10, 14.0);
+        let result = project_item(&item, &[], today, date(2), date(10));
+
+        assert_eq!(result.stockout_date, Some(date(3)));
+        assert!(result.order_overdue);
+    }
+
+    #[test]
+    fn test_request_validation() {
+        let today = date(1);
+        let request = ForecastScenarioRequest {
+            name: "Black Friday".to_string(),
+            items: vec![ScenarioItem {
+                product_id: Uuid::new_v4(),
+                location_id: Uuid::new_v4(),
+            }],
+            start_date: date(20),
+            end_date: date(30),
+            uplifts: vec![uplift(27, 29, UpliftKind::Multiplier(3.0), 1)],
+            save: false,
+        };
+        assert!(request.validate(today).is_ok());
+        assert_eq!(request.horizon_days(today), 29);
+
+        let past = ForecastScenarioRequest { end_date: today, start_date: today, ..request.clone() };
+        assert!(past.validate(today).is_err());
+
+        let mut negative = request.clone();
+        negative.uplifts[0].kind = UpliftKind::Multiplier(-1.0);
+        assert!(negative.validate(today).is_err());
+    }
+
+    #[test]
+    fn test_compare_with_actuals_covers_elapsed_days() {
+        let today = date(1);
+        let item = inputs(1000.0, 10.0, 10, 3.0);
+        let result = project_item(&item, &[], today, date(2), date(10));
+
+        let actual = HashMap::from([(date(2), 12.0), (date(3), 14.0), (date(4), 4.0)]);
+        let comparison = compare_with_actuals(&result, &actual, date(4));
+
+        assert_eq!(comparison.days_compared, 3);
+        assert_eq!(comparison.projected_demand, 30.0);
+        assert_eq!(comparison.actual_demand, 30.0);
+        assert_eq!(comparison.demand_variance_ratio, Some(0.0));
+    }
+}
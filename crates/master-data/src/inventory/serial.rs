@@ -0,0 +1,487 @@
+//! # Serial Number Tracking
+//!
+//! Unit-level tracking for products flagged `is_serialized`. Every physical
+//! unit is a [`SerialUnit`] with its current location, bin and
+//! [`SerialStatus`]; every change appends a [`SerialEvent`] to its history.
+//!
+//! ## Lifecycle
+//!
+//! ```text
+//! receive ─► in_stock ─► reserved | shipped | scrapped
+//!            reserved ─► in_stock (release) | shipped
+//!            shipped  ─► returned
+//!            returned ─► in_stock (restock) | scrapped
+//! ```
+//!
+//! Transfers move `in_stock` units between locations without changing status.
+//!
+//! Reserved units hold stock like any other reservation: reserving a unit
+//! raises `quantity_reserved` at its location, releasing or shipping it
+//! lowers it again.
+
+use crate::error::{MasterDataError, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use uuid::Uuid;
+
+/// Where a serialized unit is in its lifecycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SerialStatus {
+    InStock,
+    Reserved,
+    Shipped,
+    Returned,
+    Scrapped,
+}
+
+impl SerialStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SerialStatus::InStock => "in_stock",
+            SerialStatus::Reserved => "reserved",
+            SerialStatus::Shipped => "shipped",
+            SerialStatus::Returned => "returned",
+            SerialStatus::Scrapped => "scrapped",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "in_stock" => Some(SerialStatus::InStock),
+            "reserved" => Some(SerialStatus::Reserved),
+            "shipped" => Some(SerialStatus::Shipped),
+            "returned" => Some(SerialStatus::Returned),
+            "scrapped" => Some(SerialStatus::Scrapped),
+            _ => None,
+        }
+    }
+
+    /// Allowed lifecycle moves; everything else is rejected by the service layer
+    pub fn can_transition_to(self, next: SerialStatus) -> bool {
+        use SerialStatus::*;
+        matches!(
+            (self, next),
+            (InStock, Reserved)
+                | (InStock, Shipped)
+                | (InStock, Scrapped)
+                | (Reserved, InStock)
+                | (Reserved, Shipped)
+                | (Shipped, Returned)
+                | (Returned, InStock)
+                | (Returned, Scrapped)
+        )
+    }
+
+    /// Whether a unit in this status is physically at its location and counts
+    /// towards the location's on-hand quantity
+    pub fn is_on_hand(self) -> bool {
+        matches!(self, SerialStatus::InStock | SerialStatus::Reserved | SerialStatus::Returned)
+    }
+}
+
+/// A single serialized unit of a product
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SerialUnit {
+    pub id: Uuid,
+    pub tenant_id: Uuid,
+    pub product_id: Uuid,
+    pub serial_number: String,
+    /// `None` once the unit has left the building (shipped)
+    pub location_id: Option<Uuid>,
+    pub bin_location: Option<String>,
+    pub status: SerialStatus,
+    /// Inventory movement that originally received this unit
+    pub receipt_movement_id: Uuid,
+    pub received_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// One entry in a unit's history
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SerialEvent {
+    pub id: Uuid,
+    pub tenant_id: Uuid,
+    pub serial_unit_id: Uuid,
+    pub serial_number: String,
+    pub product_id: Uuid,
+    /// `None` for the receipt that created the unit
+    pub from_status: Option<SerialStatus>,
+    pub to_status: SerialStatus,
+    pub from_location_id: Option<Uuid>,
+    pub to_location_id: Option<Uuid>,
+    pub movement_id: Option<Uuid>,
+    pub reference: Option<String>,
+    pub performed_by: Uuid,
+    pub occurred_at: DateTime<Utc>,
+}
+
+/// Who/what caused a change, recorded on every event it produces
+#[derive(Debug, Clone)]
+pub struct SerialEventContext {
+    pub tenant_id: Uuid,
+    pub movement_id: Option<Uuid>,
+    pub reference: Option<String>,
+    pub performed_by: Uuid,
+    pub occurred_at: DateTime<Utc>,
+}
+
+/// A unit with its full history, oldest event first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SerialHistory {
+    pub unit: SerialUnit,
+    pub events: Vec<SerialEvent>,
+}
+
+impl SerialUnit {
+    /// Create a unit for an inbound receipt together with its first history event
+    pub fn receive(
+        product_id: Uuid,
+        serial_number: String,
+        location_id: Uuid,
+        bin_location: Option<String>,
+        receipt_movement_id: Uuid,
+        context: &SerialEventContext,
+    ) -> (Self, SerialEvent) {
+        let unit = Self {
+            id: Uuid::new_v4(),
+            tenant_id: context.tenant_id,
+            product_id,
+            serial_number,
+            location_id: Some(location_id),
+            bin_location,
+            status: SerialStatus::InStock,
+            receipt_movement_id,
+            received_at: context.occurred_at,
+            updated_at: context.occurred_at,
+        };
+        let event = unit.event(None, None, context);
+        (unit, event)
+    }
+
+    /// Move the unit to `next`, optionally to a new location.
+    ///
+    /// Shipping clears the location; all other moves keep the current location
+    /// unless one is given.
+    pub fn transition(
+        &mut self,
+        next: SerialStatus,
+        location_id: Option<Uuid>,
+        context: &SerialEventContext,
+    ) -> Result<SerialEvent> {
+        if !self.status.can_transition_to(next) {
+            return Err(MasterDataError::InvalidSerialTransition {
+                serial: self.serial_number.clone(),
+                from: self.status.as_str().to_string(),
+                to: next.as_str().to_string(),
+            });
+        }
+
+        let from_status = self.status;
+        let from_location_id = self.location_id;
+        self.status = next;
+        if next == SerialStatus::Shipped {
+            self.location_id = None;
+            self.bin_location = None;
+        } else if let Some(location_id) = location_id {
+            if Some(location_id) != from_location_id {
+                self.bin_location = None;
+            }
+            self.location_id = Some(location_id);
+        }
+        self.updated_at = context.occurred_at;
+
+        Ok(self.event(Some(from_status), from_location_id, context))
+    }
+
+    /// Move an in-stock unit to another location (transfers)
+    pub fn relocate(
+        &mut self,
+        location_id: Uuid,
+        bin_location: Option<String>,
+        context: &SerialEventContext,
+    ) -> Result<SerialEvent> {
+        if self.status != SerialStatus::InStock {
+            return Err(MasterDataError::InvalidSerialTransition {
+                serial: self.serial_number.clone(),
+                from: self.status.as_str().to_string(),
+                to: "transfer".to_string(),
+            });
+        }
+
+        let from_location_id = self.location_id;
+        self.location_id = Some(location_id);
+        self.bin_location = bin_location;
+        self.updated_at = context.occurred_at;
+
+        Ok(self.event(Some(SerialStatus::InStock), from_location_id, context))
+    }
+
+    fn event(
+        &self,
+        from_status: Option<SerialStatus>,
+        from_location_id: Option<Uuid>,
+        context: &SerialEventContext,
+    ) -> SerialEvent {
+        SerialEvent {
+            id: Uuid::new_v4(),
+            tenant_id: self.tenant_id,
+            serial_unit_id: self.id,
+            serial_number: self.serial_number.clone(),
+            product_id: self.product_id,
+            from_status,
+            to_status: self.status,
+            from_location_id,
+            to_location_id: self.location_id,
+            movement_id: context.movement_id,
+            reference: context.reference.clone(),
+            performed_by: context.performed_by,
+            occurred_at: context.occurred_at,
+        }
+    }
+}
+
+fn serial_error(message: impl Into<String>) -> MasterDataError {
+    MasterDataError::ValidationError {
+        field: "serial_numbers".to_string(),
+        message: message.into(),
+    }
+}
+
+/// Trim serial numbers and reject blanks and repeats within one request
+pub fn normalize_serials(serial_numbers: &[String]) -> Result<Vec<String>> {
+    let mut seen = HashSet::new();
+    let mut normalized = Vec::with_capacity(serial_numbers.len());
+    for serial in serial_numbers {
+        let serial = serial.trim();
+        if serial.is_empty() {
+            return Err(serial_error("Serial numbers cannot be blank"));
+        }
+        if !seen.insert(serial.to_string()) {
+            return Err(MasterDataError::DuplicateSerialNumber { serial: serial.to_string() });
+        }
+        normalized.push(serial.to_string());
+    }
+    Ok(normalized)
+}
+
+/// Validate the serials of an inbound movement: one per unit, no repeats
+pub fn validate_inbound_serials(quantity: i32, serial_numbers: &[String]) -> Result<Vec<String>> {
+    if quantity <= 0 {
+        return Err(MasterDataError::ValidationError {
+            field: "quantity".to_string(),
+            message: "Inbound quantity must be positive".to_string(),
+        });
+    }
+
+    let serials = normalize_serials(serial_numbers)?;
+    if serials.len() != quantity as usize {
+        return Err(serial_error(format!(
+            "Serialized product requires {} serial numbers, got {}",
+            quantity,
+            serials.len()
+        )));
+    }
+    Ok(serials)
+}
+
+/// Reject inbound serials that already exist anywhere in the tenant
+pub fn ensure_serials_are_new(serials: &[String], existing: &[String]) -> Result<()> {
+    match serials.iter().find(|serial| existing.contains(serial)) {
+        Some(serial) => Err(MasterDataError::DuplicateSerialNumber { serial: serial.clone() }),
+        None => Ok(()),
+    }
+}
+
+/// Pick the units an outbound movement applies to.
+///
+/// `candidates` are the units eligible for the movement (right product,
+/// location and status). Explicitly requested serials are used as given;
+/// without them, `auto_pick` selects the oldest receipts first (FIFO).
+/// Serialized products never fall back to an implicit pick.
+pub fn select_serials(
+    candidates: &[SerialUnit],
+    quantity: i32,
+    requested: &[String],
+    auto_pick: bool,
+) -> Result<Vec<SerialUnit>> {
+    if quantity <= 0 {
+        return Err(MasterDataError::ValidationError {
+            field: "quantity".to_string(),
+            message: "Outbound quantity must be positive".to_string(),
+        });
+    }
+
+    if !requested.is_empty() {
+        let serials = normalize_serials(requested)?;
+        if serials.len() != quantity as usize {
+            return Err(serial_error(format!(
+                "Serialized product requires {} serial numbers, got {}",
+                quantity,
+                serials.len()
+            )));
+        }
+        return serials
+            .iter()
+            .map(|serial| {
+                candidates
+                    .iter()
+                    .find(|unit| &unit.serial_number == serial)
+                    .cloned()
+                    .ok_or_else(|| serial_error(format!("Serial {} is not available for this movement", serial)))
+            })
+            .collect();
+    }
+
+    if !auto_pick {
+        return Err(serial_error(
+            "Serialized product requires explicit serial numbers; set auto_pick to pick FIFO",
+        ));
+    }
+
+    if candidates.len() < quantity as usize {
+        return Err(serial_error(format!(
+            "Only {} serialized units available, {} requested",
+            candidates.len(),
+            quantity
+        )));
+    }
+
+    let mut picked = candidates.to_vec();
+    picked.sort_by(|a, b| {
+        a.received_at
+            .cmp(&b.received_at)
+            .then_with(|| a.serial_number.cmp(&b.serial_number))
+    });
+    picked.truncate(quantity as usize);
+    Ok(picked)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn context() -> SerialEventContext {
+        SerialEventContext {
+            tenant_id: Uuid::new_v4(),
+            movement_id: Some(Uuid::new_v4()),
+            reference: None,
+            performed_by: Uuid::new_v4(),
+            occurred_at: Utc::now(),
+        }
+    }
+
+    fn serials(values: &[&str]) -> Vec<String> {
+        values.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_inbound_quantity_mismatch_rejected() {
+        let err = validate_inbound_serials(3, &serials(&["SN-1", "SN-2"])).unwrap_err();
+        assert!(matches!(err, MasterDataError::ValidationError { ref field, .. } if field == "serial_numbers"));
+
+        assert!(validate_inbound_serials(0, &[]).is_err());
+        assert_eq!(
+            validate_inbound_serials(2, &serials(&[" SN-1 ", "SN-2"])).unwrap(),
+            serials(&["SN-1", "SN-2"])
+        );
+    }
+
+    #[test]
+    fn test_duplicate_serial_rejected() {
+        let err = validate_inbound_serials(2, &serials(&["SN-1", " SN-1"])).unwrap_err();
+        assert!(matches!(err, MasterDataError::DuplicateSerialNumber { ref serial } if serial == "SN-1"));
+
+        let err = ensure_serials_are_new(&serials(&["SN-1", "SN-2"]), &serials(&["SN-2"])).unwrap_err();
+        assert!(matches!(err, MasterDataError::DuplicateSerialNumber { ref serial } if serial == "SN-2"));
+        assert!(ensure_serials_are_new(&serials(&["SN-3"]), &serials(&["SN-2"])).is_ok());
+    }
+
+    #[test]
+    fn test_receive_reserve_ship_lifecycle() {
+        let product_id = Uuid::new_v4();
+        let location_id = Uuid::new_v4();
+        let receipt = context();
+        let receipt_movement = receipt.movement_id.unwrap();
+
+        let inbound = validate_inbound_serials(2, &serials(&["SN-1", "SN-2"])).unwrap();
+        let (mut units, events): (Vec<_>, Vec<_>) = inbound
+            .into_iter()
+            .map(|serial| SerialUnit::receive(product_id, serial, location_id, Some("A-01".to_string()), receipt_movement, &receipt))
+            .unzip();
+        assert!(events.iter().all(|e| e.from_status.is_none() && e.to_status == SerialStatus::InStock));
+
+        // Reserve one unit by auto-pick
+        let mut unit = select_serials(&units, 1, &[], true).unwrap().remove(0);
+        let reserve = unit.transition(SerialStatus::Reserved, None, &context()).unwrap();
+        assert_eq!(reserve.from_status, Some(SerialStatus::InStock));
+        assert_eq!(unit.location_id, Some(location_id));
+        units.retain(|u| u.id != unit.id);
+
+        // Ship it
+        let ship = unit.transition(SerialStatus::Shipped, None, &context()).unwrap();
+        assert_eq!(ship.from_status, Some(SerialStatus::Reserved));
+        assert_eq!(ship.from_location_id, Some(location_id));
+        assert_eq!(unit.status, SerialStatus::Shipped);
+        assert_eq!(unit.location_id, None);
+        assert_eq!(unit.receipt_movement_id, receipt_movement);
+
+        // Shipped units cannot be reserved or transferred
+        assert!(matches!(
+            unit.transition(SerialStatus::Reserved, None, &context()),
+            Err(MasterDataError::InvalidSerialTransition { .. })
+        ));
+        assert!(unit.relocate(Uuid::new_v4(), None, &context()).is_err());
+
+        // The other unit is still in stock and can move to another location
+        let other_location = Uuid::new_v4();
+        let transfer = units[0].relocate(other_location, None, &context()).unwrap();
+        assert_eq!(transfer.from_location_id, Some(location_id));
+        assert_eq!(transfer.to_location_id, Some(other_location));
+    }
+
+    #[test]
+    fn test_transition_table() {
+        use SerialStatus::*;
+        assert!(InStock.can_transition_to(Reserved));
+        assert!(Reserved.can_transition_to(InStock));
+        assert!(Shipped.can_transition_to(Returned));
+        assert!(Returned.can_transition_to(Scrapped));
+        assert!(!Shipped.can_transition_to(InStock));
+        assert!(!Reserved.can_transition_to(Scrapped));
+        assert!(!Scrapped.can_transition_to(InStock));
+        for status in [InStock, Reserved, Shipped, Returned, Scrapped] {
+            assert_eq!(SerialStatus::parse(status.as_str()), Some(status));
+        }
+    }
+
+    #[test]
+    fn test_outbound_requires_selection_or_fifo_override() {
+        let ctx = context();
+        let location_id = Uuid::new_v4();
+        let units: Vec<SerialUnit> = ["SN-B", "SN-A", "SN-C"]
+            .iter()
+            .enumerate()
+            .map(|(i, serial)| {
+                let mut ctx = ctx.clone();
+                ctx.occurred_at += Duration::days(i as i64);
+                SerialUnit::receive(Uuid::new_v4(), serial.to_string(), location_id, None, Uuid::new_v4(), &ctx).0
+            })
+            .collect();
+
+        assert!(select_serials(&units, 1, &[], false).is_err());
+
+        let picked = select_serials(&units, 2, &[], true).unwrap();
+        assert_eq!(picked[0].serial_number, "SN-B");
+        assert_eq!(picked[1].serial_number, "SN-A");
+
+        let explicit = select_serials(&units, 1, &serials(&["SN-C"]), false).unwrap();
+        assert_eq!(explicit[0].serial_number, "SN-C");
+
+        assert!(select_serials(&units, 1, &serials(&["SN-X"]), false).is_err());
+        assert!(select_serials(&units, 2, &serials(&["SN-C"]), false).is_err());
+        assert!(select_serials(&units, 4, &[], true).is_err());
+    }
+}
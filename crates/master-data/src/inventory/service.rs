@@ -4,15 +4,65 @@
 //! with real-time tracking, demand forecasting, and automated optimization.
 
 use crate::inventory::model::*;
-use crate::inventory::repository::InventoryRepository;
-use crate::types::{ValuationMethod, ReservationType};
+use crate::inventory::optimization::InventoryOptimizationEngine;
+use crate::inventory::capacity::{
+    self, CapacityAssessment, CapacitySettings, CapacityUnit, IncomingQuantity, LocationCapacity, LocationCapacityInput,
+    CAPACITY_OVERRIDE_PERMISSION,
+};
+use crate::inventory::receiving::{
+    self, PurchaseOrderReceipt, ReceivableOrder, ReceivePurchaseOrderRequest, ReceiptHistory, ReceivingSettings,
+};
+use crate::inventory::consignment::{
+    self, ConsignmentBalance, ConsignmentConsumptionRequest, ConsignmentMovement, ConsignmentReceiptRequest,
+    ConsignmentSettlement, ConsignmentTransferRequest,
+};
+use crate::inventory::atp::{self, AtpBatchLine, AtpLineResult, AtpTimeline, MAX_BATCH_LINES};
+use crate::inventory::costing::{self, CostRestatement, CostRestatementRequest, CostSourceReport, CostingSettings};
+use crate::inventory::snapshot::{self, SnapshotComparison, SnapshotLine, SnapshotRun, SnapshotSchedule, SnapshotScope, SnapshotTrigger};
+use crate::inventory::repository::{
+    AvailableToPromiseRepository, ConsignmentRepository, InventoryCostingRepository, InventorySnapshotRepository, ForecastScenarioRepository, InventoryRepository, InventoryValuationRepository, LocationCapacityRepository,
+    PickWaveRepository, PurchaseReceiptRepository, ReturnOrderChange, ReturnOrderRepository, SerialChangeSet,
+    SerialUnitRepository, StockTransferRepository, StockValueFilter, StocktakeRepository,
+};
+use crate::inventory::valuation::{
+    self, MarginReport, StockKpis, StockValuation, StockValueLine, ValuationDashboard,
+};
+use crate::currency::{resolve_conversion, CurrencyConversion, ExchangeRateProvider, ExchangeRateRepository};
+use crate::inventory::picking::{
+    ConfirmPickRequest, CreatePickWaveRequest, PickLine, PickPathStrategy, PickWave, SerpentinePath, WaveStatus,
+    WaveSummary,
+};
+use crate::inventory::returns::{
+    self, CreateReturnOrderRequest, Disposition, InspectReturnRequest, ProductReturnRate, ReceiveReturnRequest,
+    ReturnOrder, ReturnStatus,
+};
+use crate::inventory::scenario::{
+    self, ForecastScenario, ForecastScenarioComparison, ForecastScenarioRequest, IncomingSupply, ScenarioItem,
+};
+use crate::inventory::serial::*;
+use crate::inventory::transfer_approval::{
+    self, ApprovalDecision, ReservationChange, TransferApprovalRule, TransferListFilter, TransferRecord,
+    TRANSFER_APPROVAL_PERMISSION, TRANSFER_RULES_PERMISSION,
+};
+use crate::inventory::stocktake::{
+    self, CreateStocktakeSessionRequest, LocationShrinkage, StocktakeImportResult, StocktakeSession, StocktakeStatus,
+};
+use crate::product::{ProductRepository, QualityInspectionService};
+use crate::spans;
+use crate::supplier::{CatalogQuote, CatalogQuoteRequest, SupplierCatalogService};
+use crate::types::{ValuationMethod, ReservationType, TenantContext};
 use crate::error::{Result, MasterDataError};
 use async_trait::async_trait;
-use chrono::{DateTime, Utc, Duration};
+use chrono::{DateTime, NaiveDate, Utc, Duration};
+use erp_core::actor::ActorContext;
+use erp_core::latency::LatencyBudget;
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use std::sync::Arc;
 use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use tracing::Instrument;
 
 // Request DTOs for service operations
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -24,6 +74,13 @@ pub struct CreateStockTransferRequest {
     pub priority: TransferPriority,
     pub requested_date: DateTime<Utc>,
     pub notes: Option<String>,
+    /// When the goods are expected at the target location
+    #[serde(default)]
+    pub expected_arrival_date: Option<NaiveDate>,
+    /// Create the transfer even if the target location or bin ends up over
+    /// capacity; requires `inventory:capacity_override`
+    #[serde(default)]
+    pub override_capacity: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -36,6 +93,7 @@ pub struct CreateReservationRequest {
     pub priority: ReservationPriority,
     pub reserved_until: DateTime<Utc>,
     pub notes: Option<String>,
+    pub created_by: Uuid,
 }
 
 
@@ -47,9 +105,10 @@ pub trait InventoryService: Send + Sync {
     async fn get_all_location_inventories(&self, product_id: Uuid) -> Result<Vec<LocationInventory>>;
     async fn update_inventory_levels(&self, request: UpdateInventoryRequest) -> Result<LocationInventory>;
     async fn get_inventory_by_location(&self, location_id: Uuid) -> Result<Vec<LocationInventory>>;
+    async fn get_stock_availability(&self, pairs: &[(Uuid, Uuid)]) -> Result<Vec<StockAvailability>>;
 
     // === Stock Transfer Management ===
-    async fn create_stock_transfer(&self, request: CreateStockTransferRequest) -> Result<StockTransfer>;
+    // Transfers are created through `StockTransferService`, which holds them for approval
     async fn approve_stock_transfer(&self, transfer_id: Uuid, approved_by: Uuid) -> Result<StockTransfer>;
     async fn process_transfer_shipment(&self, transfer_id: Uuid, shipped_by: Uuid) -> Result<StockTransfer>;
     async fn receive_transfer(&self, transfer_id: Uuid, received_by: Uuid, actual_quantity: i32) -> Result<StockTransfer>;
@@ -66,8 +125,6 @@ pub trait InventoryService: Send + Sync {
     async fn update_replenishment_rule(&self, rule_id: Uuid, request: UpdateReplenishmentRuleRequest) -> Result<ReplenishmentRule>;
     async fn get_replenishment_suggestions(&self, location_id: Option<Uuid>) -> Result<Vec<ReplenishmentSuggestion>>;
     async fn auto_generate_purchase_orders(&self, location_id: Uuid) -> Result<Vec<PurchaseOrder>>;
-
-    // === Cycle Counting & Accuracy ===
     async fn create_cycle_count(&self, request: CycleCountRequest) -> Result<CycleCount>;
     async fn process_cycle_count_variance(&self, count_id: Uuid, approved_by: Uuid) -> Result<CycleCount>;
     async fn schedule_cycle_counts(&self, location_id: Uuid, count_type: CycleCountType) -> Result<Vec<CycleCount>>;
@@ -308,11 +365,85 @@ pub struct TransferOpportunity {
 /// Production-ready inventory service implementation
 pub struct DefaultInventoryService {
     repository: Arc<dyn InventoryRepository>,
+    supplier_catalog: Option<Arc<dyn SupplierCatalogService>>,
+    exchange_rates: Option<Arc<dyn ExchangeRateRepository>>,
+    tenant_id: Option<Uuid>,
+    actor: ActorContext,
+    latency: LatencyBudget,
 }
 
 impl DefaultInventoryService {
     pub fn new(repository: Arc<dyn InventoryRepository>) -> Self {
-        Self { repository }
+        Self {
+            repository,
+            supplier_catalog: None,
+            exchange_rates: None,
+            tenant_id: None,
+            actor: ActorContext::default(),
+            latency: LatencyBudget::default(),
+        }
+    }
+
+    /// Tenant the repository is scoped to, recorded in the spans of the service's methods
+    pub fn with_tenant(mut self, tenant_id: Uuid) -> Self {
+        self.tenant_id = Some(tenant_id);
+        self
+    }
+
+    /// Who rules, counts and generated orders are recorded as made by;
+    /// without one these writes fail
+    pub fn with_actor(mut self, actor: ActorContext) -> Self {
+        self.actor = actor;
+        self
+    }
+
+    /// Time methods against these budgets instead of the default ones
+    pub fn with_latency_budget(mut self, latency: LatencyBudget) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    /// Price generated purchase orders from supplier catalogs instead of replenishment estimates
+    pub fn with_supplier_catalog(mut self, supplier_catalog: Arc<dyn SupplierCatalogService>) -> Self {
+        self.supplier_catalog = Some(supplier_catalog);
+        self
+    }
+
+    /// Order lines without a catalog quote in the tenant's base currency; without
+    /// this (and a tenant) such lines cannot be ordered
+    pub fn with_exchange_rates(mut self, exchange_rates: Arc<dyn ExchangeRateRepository>) -> Self {
+        self.exchange_rates = Some(exchange_rates);
+        self
+    }
+
+    /// Currency replenishment estimates are in
+    async fn base_currency(&self) -> Result<String> {
+        match (&self.exchange_rates, self.tenant_id) {
+            (Some(rates), Some(tenant_id)) => rates.base_currency(tenant_id).await,
+            _ => Err(MasterDataError::ValidationError {
+                field: "currency".to_string(),
+                message: "The tenant's base currency is needed to order without a catalog quote".to_string(),
+            }),
+        }
+    }
+
+    /// Best catalog quote for a replenishment suggestion.
+    ///
+    /// The rule's preferred supplier acts as an override; the rule's lead time
+    /// is the limit entries should meet.
+    async fn quote_suggestion(&self, suggestion: &ReplenishmentSuggestion, as_of: DateTime<Utc>) -> Result<Option<CatalogQuote>> {
+        let catalog = match &self.supplier_catalog {
+            Some(catalog) if suggestion.suggested_order_quantity > 0 => catalog,
+            _ => return Ok(None),
+        };
+
+        let request = CatalogQuoteRequest {
+            quantity: suggestion.suggested_order_quantity,
+            as_of: Some(as_of),
+            max_lead_time_days: Some(suggestion.lead_time_days),
+            supplier_override: suggestion.supplier_id,
+        };
+        Ok(catalog.quote_product(suggestion.product_id, request).await?)
     }
 
     /// Calculate optimal stock levels using advanced algorithms
@@ -492,15 +623,32 @@ pub struct OptimalStockCalculation {
 
 #[async_trait]
 impl InventoryService for DefaultInventoryService {
+    #[tracing::instrument(skip_all, fields(
+        tenant_id = self.tenant_id.map(tracing::field::display),
+        product_id = %product_id,
+        location_id = %location_id
+    ))]
     async fn get_location_inventory(&self, product_id: Uuid, location_id: Uuid) -> Result<LocationInventory> {
-        self.repository.get_location_inventory(product_id, location_id).await
+        let _latency = self.latency.start("inventory.read", "get_location_inventory");
+        self.repository.get_location_inventory(product_id, location_id)
+            .instrument(spans::repository("get_location_inventory"))
+            .await
     }
 
+    #[tracing::instrument(skip_all, fields(
+        tenant_id = self.tenant_id.map(tracing::field::display),
+        product_id = %product_id
+    ))]
     async fn get_all_location_inventories(&self, product_id: Uuid) -> Result<Vec<LocationInventory>> {
-        self.repository.get_all_location_inventories(product_id).await
+        let _latency = self.latency.start("inventory.read", "get_all_location_inventories");
+        self.repository.get_all_location_inventories(product_id)
+            .instrument(spans::repository("get_all_location_inventories"))
+            .await
     }
 
+    #[tracing::instrument(skip_all, fields(tenant_id = self.tenant_id.map(tracing::field::display)))]
     async fn update_inventory_levels(&self, request: UpdateInventoryRequest) -> Result<LocationInventory> {
+        let _latency = self.latency.start("inventory.write", "update_inventory_levels");
         // Validate the request
         if request.quantity_change == 0 {
             return Err(MasterDataError::ValidationError {
@@ -510,94 +658,77 @@ impl InventoryService for DefaultInventoryService {
         }
 
         // Update inventory and create movement record
-        self.repository.update_inventory_levels(
-            request.location_id,
-            // Assuming we need product_id - would need to be in the request
-            Uuid::new_v4(), // Placeholder
-            request,
-        ).await
+        self.repository.update_inventory_levels(request.location_id, request.product_id, request)
+            .instrument(spans::repository("update_inventory_levels"))
+            .await
     }
 
+    #[tracing::instrument(skip_all, fields(
+        tenant_id = self.tenant_id.map(tracing::field::display),
+        location_id = %location_id
+    ))]
     async fn get_inventory_by_location(&self, location_id: Uuid) -> Result<Vec<LocationInventory>> {
-        self.repository.get_inventory_by_location(location_id).await
+        let _latency = self.latency.start("inventory.read", "get_inventory_by_location");
+        self.repository.get_inventory_by_location(location_id)
+            .instrument(spans::repository("get_inventory_by_location"))
+            .await
     }
 
-    async fn create_stock_transfer(&self, request: CreateStockTransferRequest) -> Result<StockTransfer> {
-        // Validate transfer request
-        if request.from_location_id == request.to_location_id {
-            return Err(MasterDataError::ValidationError { field: "location".to_string(), message: "Cannot transfer to the same location".to_string() }.into());
-        }
-
-        if request.quantity <= 0 {
-            return Err(MasterDataError::ValidationError { field: "quantity".to_string(), message: "Transfer quantity must be positive".to_string() }.into());
-        }
-
-        // Check available inventory
-        let from_inventory = self.repository
-            .get_location_inventory(request.product_id, request.from_location_id)
-            .await?;
-
-        if from_inventory.quantity_available < request.quantity {
-            return Err(MasterDataError::ValidationError { field: "quantity".to_string(), message: "Insufficient inventory for transfer".to_string() }.into());
-        }
-
-        // Create transfer record
-        let transfer = StockTransfer {
-            id: Uuid::new_v4(),
-            product_id: request.product_id,
-            from_location_id: request.from_location_id,
-            to_location_id: request.to_location_id,
-            quantity: request.quantity,
-            quantity_shipped: None,
-            quantity_received: None,
-            status: TransferStatus::Requested,
-            priority: request.priority,
-            reason: "Manual transfer".to_string(),
-            requested_by: Uuid::new_v4(), // Would come from context
-            approved_by: None,
-            shipped_by: None,
-            received_by: None,
-            requested_date: Utc::now(),
-            approved_date: None,
-            shipped_date: None,
-            received_date: None,
-            actual_delivery_date: None,
-            tracking_number: None,
-            carrier: None,
-            shipping_cost: None,
-            notes: request.notes,
-            created_at: Utc::now(),
-            created_by: Uuid::new_v4(), // Would come from context
-        };
-
-        self.repository.create_stock_transfer(transfer).await
+    #[tracing::instrument(skip_all, fields(tenant_id = self.tenant_id.map(tracing::field::display)))]
+    async fn get_stock_availability(&self, pairs: &[(Uuid, Uuid)]) -> Result<Vec<StockAvailability>> {
+        let _latency = self.latency.start("inventory.read", "get_stock_availability");
+        self.repository.get_stock_availability(pairs).instrument(spans::repository("get_stock_availability")).await
     }
 
+    #[tracing::instrument(skip_all, fields(
+        tenant_id = self.tenant_id.map(tracing::field::display),
+        transfer_id = %transfer_id
+    ))]
     async fn approve_stock_transfer(&self, transfer_id: Uuid, approved_by: Uuid) -> Result<StockTransfer> {
+        let _latency = self.latency.start("inventory.write", "approve_stock_transfer");
         self.repository.update_stock_transfer(
             transfer_id,
             TransferStatus::Approved,
             Some(format!("Approved by {}", approved_by)),
-        ).await
+        ).instrument(spans::repository("update_stock_transfer")).await
     }
 
+    #[tracing::instrument(skip_all, fields(
+        tenant_id = self.tenant_id.map(tracing::field::display),
+        transfer_id = %transfer_id
+    ))]
     async fn process_transfer_shipment(&self, transfer_id: Uuid, shipped_by: Uuid) -> Result<StockTransfer> {
+        let _latency = self.latency.start("inventory.write", "process_transfer_shipment");
         self.repository.update_stock_transfer(
             transfer_id,
             TransferStatus::InTransit,
             Some(format!("Shipped by {}", shipped_by)),
-        ).await
+        ).instrument(spans::repository("update_stock_transfer")).await
     }
 
+    #[tracing::instrument(skip_all, fields(
+        tenant_id = self.tenant_id.map(tracing::field::display),
+        transfer_id = %transfer_id
+    ))]
     async fn receive_transfer(&self, transfer_id: Uuid, received_by: Uuid, actual_quantity: i32) -> Result<StockTransfer> {
-        self.repository.process_transfer_receipt(transfer_id, actual_quantity, received_by).await
+        let _latency = self.latency.start("inventory.write", "receive_transfer");
+        self.repository.process_transfer_receipt(transfer_id, actual_quantity, received_by)
+            .instrument(spans::repository("process_transfer_receipt"))
+            .await
     }
 
+    #[tracing::instrument(skip_all, fields(
+        tenant_id = self.tenant_id.map(tracing::field::display),
+        location_id = location_id.map(tracing::field::display)
+    ))]
     async fn get_pending_transfers(&self, location_id: Option<Uuid>) -> Result<Vec<StockTransfer>> {
-        self.repository.get_pending_transfers(location_id).await
+        let _latency = self.latency.start("inventory.read", "get_pending_transfers");
+        self.repository.get_pending_transfers(location_id).instrument(spans::repository("get_pending_transfers")).await
     }
 
+    #[tracing::instrument(skip_all, fields(tenant_id = self.tenant_id.map(tracing::field::display)))]
     async fn create_reservation(&self, request: CreateReservationRequest) -> Result<InventoryReservation> {
+        let _latency = self.latency.start("inventory.write", "create_reservation");
         // Validate reservation request
         if request.quantity <= 0 {
             return Err(MasterDataError::ValidationError { field: "quantity".to_string(), message: "Reservation quantity must be positive".to_string() }.into());
@@ -606,6 +737,7 @@ impl InventoryService for DefaultInventoryService {
         // Check available inventory
         let inventory = self.repository
             .get_location_inventory(request.product_id, request.location_id)
+            .instrument(spans::repository("get_location_inventory"))
             .await?;
 
         let available_for_reservation = inventory.quantity_available - inventory.quantity_reserved;
@@ -620,14 +752,14 @@ impl InventoryService for DefaultInventoryService {
             location_id: request.location_id,
             quantity_reserved: request.quantity,
             reservation_status: ReservationStatus::Active,
-            priority: ReservationPriority::Normal, // Default priority
-            reference_id: Uuid::new_v4(), // Default reference
+            priority: request.priority,
+            reference_id: request.reference_id,
             reference_type: "manual".to_string(),
-            expiry_date: None, // Default no expiry
+            expiry_date: Some(request.reserved_until),
             created_at: Utc::now(),
             updated_at: Utc::now(),
-            notes: None,
-            created_by: Uuid::new_v4(), // Would come from context
+            notes: request.notes,
+            created_by: request.created_by,
             released_at: None,
             released_by: None,
             quantity: request.quantity, // Alias field
@@ -638,24 +770,48 @@ impl InventoryService for DefaultInventoryService {
             reserved_until: Some(request.reserved_until),
         };
 
-        self.repository.create_reservation(reservation).await
+        self.repository.create_reservation(reservation).instrument(spans::repository("create_reservation")).await
     }
 
+    #[tracing::instrument(skip_all, fields(
+        tenant_id = self.tenant_id.map(tracing::field::display),
+        reservation_id = %reservation_id
+    ))]
     async fn release_reservation(&self, reservation_id: Uuid, released_by: Uuid) -> Result<InventoryReservation> {
-        self.repository.release_reservation(reservation_id, released_by).await
+        let _latency = self.latency.start("inventory.write", "release_reservation");
+        self.repository.release_reservation(reservation_id, released_by)
+            .instrument(spans::repository("release_reservation"))
+            .await
     }
 
-    async fn fulfill_reservation(&self, reservation_id: Uuid, _fulfilled_by: Uuid) -> Result<InventoryReservation> {
+    #[tracing::instrument(skip_all, fields(
+        tenant_id = self.tenant_id.map(tracing::field::display),
+        reservation_id = %reservation_id
+    ))]
+    async fn fulfill_reservation(&self, reservation_id: Uuid, fulfilled_by: Uuid) -> Result<InventoryReservation> {
+        let _latency = self.latency.start("inventory.write", "fulfill_reservation");
         // Update reservation status and adjust inventory
         // Implementation would handle the fulfillment logic
-        self.repository.release_reservation(reservation_id, Uuid::new_v4()).await
+        self.repository.release_reservation(reservation_id, fulfilled_by)
+            .instrument(spans::repository("release_reservation"))
+            .await
     }
 
+    #[tracing::instrument(skip_all, fields(
+        tenant_id = self.tenant_id.map(tracing::field::display),
+        product_id = %product_id,
+        location_id = %location_id
+    ))]
     async fn get_active_reservations(&self, product_id: Uuid, location_id: Uuid) -> Result<Vec<InventoryReservation>> {
-        self.repository.get_active_reservations(product_id, location_id).await
+        let _latency = self.latency.start("inventory.read", "get_active_reservations");
+        self.repository.get_active_reservations(product_id, location_id)
+            .instrument(spans::repository("get_active_reservations"))
+            .await
     }
 
+    #[tracing::instrument(skip_all, fields(tenant_id = self.tenant_id.map(tracing::field::display)))]
     async fn create_replenishment_rule(&self, request: CreateReplenishmentRuleRequest) -> Result<ReplenishmentRule> {
+        let _latency = self.latency.start("inventory.write", "create_replenishment_rule");
         let rule = ReplenishmentRule {
             id: Uuid::new_v4(),
             product_id: request.product_id,
@@ -682,76 +838,148 @@ impl InventoryService for DefaultInventoryService {
             preferred_supplier_id: request.supplier_id,
             is_active: true,
             last_triggered: None,
-            created_by: Uuid::new_v4(), // Default user ID - should come from context
+            created_by: self.actor.id()?,
         };
 
-        self.repository.create_replenishment_rule(rule).await
+        self.repository.create_replenishment_rule(rule).instrument(spans::repository("create_replenishment_rule")).await
     }
 
+    #[tracing::instrument(skip_all, fields(
+        tenant_id = self.tenant_id.map(tracing::field::display),
+        rule_id = %rule_id
+    ))]
     async fn update_replenishment_rule(&self, rule_id: Uuid, request: UpdateReplenishmentRuleRequest) -> Result<ReplenishmentRule> {
-        self.repository.update_replenishment_rule(rule_id, request).await
+        let _latency = self.latency.start("inventory.write", "update_replenishment_rule");
+        self.repository.update_replenishment_rule(rule_id, request)
+            .instrument(spans::repository("update_replenishment_rule"))
+            .await
     }
 
+    #[tracing::instrument(skip_all, fields(
+        tenant_id = self.tenant_id.map(tracing::field::display),
+        location_id = location_id.map(tracing::field::display)
+    ))]
     async fn get_replenishment_suggestions(&self, location_id: Option<Uuid>) -> Result<Vec<ReplenishmentSuggestion>> {
-        self.repository.get_replenishment_suggestions(location_id, 0.5).await
+        let _latency = self.latency.start("inventory.analytics", "get_replenishment_suggestions");
+        self.repository.get_replenishment_suggestions(location_id, 0.5)
+            .instrument(spans::repository("get_replenishment_suggestions"))
+            .await
     }
 
+    #[tracing::instrument(skip_all, fields(
+        tenant_id = self.tenant_id.map(tracing::field::display),
+        location_id = %location_id
+    ))]
     async fn auto_generate_purchase_orders(&self, location_id: Uuid) -> Result<Vec<PurchaseOrder>> {
+        let _latency = self.latency.start("inventory.write", "auto_generate_purchase_orders");
         let suggestions = self.get_replenishment_suggestions(Some(location_id)).await?;
         let mut purchase_orders = Vec::new();
+        let now = Utc::now();
 
-        // Group suggestions by supplier
-        let mut supplier_suggestions: HashMap<Option<Uuid>, Vec<ReplenishmentSuggestion>> = HashMap::new();
+        // Group suggestions by the supplier (and currency) they will be bought from
+        let mut supplier_lines: HashMap<(Uuid, String), Vec<(ReplenishmentSuggestion, Option<CatalogQuote>)>> = HashMap::new();
+        let mut base_currency = None;
         for suggestion in suggestions {
-            supplier_suggestions
-                .entry(suggestion.supplier_id)
-                .or_insert_with(Vec::new)
-                .push(suggestion);
+            let quote = self.quote_suggestion(&suggestion, now).await?;
+            let key = match (&quote, suggestion.supplier_id) {
+                (Some(quote), _) => (quote.supplier_id, quote.currency.clone()),
+                (None, Some(supplier_id)) => {
+                    let currency = match base_currency.clone() {
+                        Some(currency) => currency,
+                        None => {
+                            let currency = self.base_currency().await?;
+                            base_currency = Some(currency.clone());
+                            currency
+                        }
+                    };
+                    (supplier_id, currency)
+                }
+                (None, None) => continue,
+            };
+            supplier_lines.entry(key).or_default().push((suggestion, quote));
         }
 
         // Create purchase orders for each supplier
-        for (supplier_id, supplier_suggestions) in supplier_suggestions {
-            if let Some(supplier_id) = supplier_id {
-                let total_amount: f64 = supplier_suggestions.iter()
-                    .map(|s| s.estimated_cost)
-                    .sum();
+        for ((supplier_id, currency), lines) in supplier_lines {
+            let priced_lines: Vec<(Uuid, i32, f64, i32)> = lines.iter()
+                .map(|(suggestion, quote)| match quote {
+                    Some(quote) => (
+                        suggestion.product_id,
+                        quote.order_quantity,
+                        quote.unit_price as f64 / 100.0,
+                        quote.lead_time_days,
+                    ),
+                    None => (
+                        suggestion.product_id,
+                        suggestion.suggested_order_quantity,
+                        suggestion.estimated_cost / suggestion.suggested_order_quantity.max(1) as f64,
+                        suggestion.lead_time_days,
+                    ),
+                })
+                .collect();
+
+            let total_amount: f64 = priced_lines.iter()
+                .map(|(_, quantity, unit_price, _)| *quantity as f64 * unit_price)
+                .sum();
+            let lead_time_days = priced_lines.iter()
+                .map(|(_, _, _, lead_time)| *lead_time)
+                .max()
+                .unwrap_or(14);
+
+            let po = PurchaseOrder {
+                id: Uuid::new_v4(),
+                order_number: format!("PO-{}", Utc::now().timestamp()),
+                supplier_id,
+                supplier_name: "Supplier Name".to_string(),
+                location_id,
+                status: OrderStatus::Draft,
+                order_date: now,
+                expected_delivery_date: Some(now + Duration::days(lead_time_days as i64)),
+                actual_delivery_date: None,
+                total_amount,
+                currency,
+                payment_terms: Some("Net 30".to_string()),
+                shipping_terms: Some("FOB".to_string()),
+                priority: Some("Normal".to_string()),
+                billing_address: Some("123 Billing St, City, State 12345".to_string()),
+                shipping_address: Some("456 Shipping Ave, City, State 67890".to_string()),
+                created_by: self.actor.id()?,
+                approved_by: None,
+                tracking_number: None,
+                notes: Some("Auto-generated from replenishment rules".to_string()),
+                created_at: now,
+                updated_at: now,
+            };
 
-                let po = PurchaseOrder {
+            let po = self.repository.create_purchase_order(po)
+                .instrument(spans::repository("create_purchase_order"))
+                .await?;
+            for (product_id, quantity, unit_price, _) in priced_lines {
+                self.repository.add_purchase_order_line(PurchaseOrderLine {
                     id: Uuid::new_v4(),
-                    order_number: format!("PO-{}", Utc::now().timestamp()),
-                    supplier_id,
-                    supplier_name: "Supplier Name".to_string(),
-                    location_id,
-                    status: OrderStatus::Draft,
-                    order_date: Utc::now(),
-                    expected_delivery_date: Some(Utc::now() + Duration::days(14)),
-                    actual_delivery_date: None,
-                    total_amount,
-                    currency: "USD".to_string(),
-                    payment_terms: Some("Net 30".to_string()),
-                    shipping_terms: Some("FOB".to_string()),
-                    priority: Some("Normal".to_string()),
-                    billing_address: Some("123 Billing St, City, State 12345".to_string()),
-                    shipping_address: Some("456 Shipping Ave, City, State 67890".to_string()),
-                    created_by: Uuid::new_v4(),
-                    approved_by: None,
-                    tracking_number: None,
-                    notes: Some("Auto-generated from replenishment rules".to_string()),
-                    created_at: Utc::now(),
-                    updated_at: Utc::now(),
-                };
-
-                purchase_orders.push(self.repository.create_purchase_order(po).await?);
+                    purchase_order_id: po.id,
+                    product_id,
+                    quantity_ordered: quantity,
+                    quantity_received: 0,
+                    unit_price,
+                    line_total: quantity as f64 * unit_price,
+                    created_at: now,
+                    updated_at: now,
+                }).instrument(spans::repository("add_purchase_order_line")).await?;
             }
+            purchase_orders.push(po);
         }
 
         Ok(purchase_orders)
     }
 
+    #[tracing::instrument(skip_all, fields(tenant_id = self.tenant_id.map(tracing::field::display)))]
     async fn create_cycle_count(&self, request: CycleCountRequest) -> Result<CycleCount> {
+        let _latency = self.latency.start("inventory.write", "create_cycle_count");
         // Get current book quantity
         let inventory = self.repository
             .get_location_inventory(request.product_id, request.location_id)
+            .instrument(spans::repository("get_location_inventory"))
             .await?;
 
         let variance = request.counted_quantity - inventory.quantity_available;
@@ -779,7 +1007,7 @@ impl InventoryService for DefaultInventoryService {
             notes: request.notes,
             created_at: Utc::now(),
             updated_at: Utc::now(),
-            created_by: Uuid::new_v4(),
+            created_by: self.actor.id()?,
             counter_name: "Counter Name".to_string(),
             book_quantity: inventory.quantity_available,
             counted_quantity: request.counted_quantity,
@@ -789,20 +1017,39 @@ impl InventoryService for DefaultInventoryService {
             adjustment_applied: false,
         };
 
-        self.repository.create_cycle_count(count).await
+        self.repository.create_cycle_count(count).instrument(spans::repository("create_cycle_count")).await
     }
 
+    #[tracing::instrument(skip_all, fields(
+        tenant_id = self.tenant_id.map(tracing::field::display),
+        count_id = %count_id
+    ))]
     async fn process_cycle_count_variance(&self, count_id: Uuid, approved_by: Uuid) -> Result<CycleCount> {
-        self.repository.apply_cycle_count_adjustment(count_id, approved_by).await
+        let _latency = self.latency.start("inventory.write", "process_cycle_count_variance");
+        self.repository.apply_cycle_count_adjustment(count_id, approved_by)
+            .instrument(spans::repository("apply_cycle_count_adjustment"))
+            .await
     }
 
+    #[tracing::instrument(skip_all, fields(
+        tenant_id = self.tenant_id.map(tracing::field::display),
+        location_id = %location_id
+    ))]
     async fn schedule_cycle_counts(&self, location_id: Uuid, _count_type: CycleCountType) -> Result<Vec<CycleCount>> {
+        let _latency = self.latency.start("inventory.write", "schedule_cycle_counts");
         // Implementation would schedule cycle counts based on ABC classification, velocity, etc.
         Ok(vec![])
     }
 
+    #[tracing::instrument(skip_all, fields(
+        tenant_id = self.tenant_id.map(tracing::field::display),
+        location_id = %location_id
+    ))]
     async fn get_inventory_accuracy(&self, location_id: Uuid) -> Result<InventoryAccuracy> {
-        let counts = self.repository.get_cycle_counts(location_id, None).await?;
+        let _latency = self.latency.start("inventory.analytics", "get_inventory_accuracy");
+        let counts = self.repository.get_cycle_counts(location_id, None)
+            .instrument(spans::repository("get_cycle_counts"))
+            .await?;
 
         let total_counts = counts.len() as i32;
         let variance_count = counts.iter().filter(|c| c.variance != 0).count() as i32;
@@ -824,7 +1071,12 @@ impl InventoryService for DefaultInventoryService {
         })
     }
 
+    #[tracing::instrument(skip_all, fields(
+        tenant_id = self.tenant_id.map(tracing::field::display),
+        location_id = %location_id
+    ))]
     async fn calculate_inventory_valuation(&self, location_id: Uuid, valuation_method: ValuationMethod) -> Result<InventoryValuation> {
+        let _latency = self.latency.start("inventory.analytics", "calculate_inventory_valuation");
         // Implementation would calculate valuation based on method
         Ok(InventoryValuation {
             id: Uuid::new_v4(),
@@ -848,12 +1100,22 @@ impl InventoryService for DefaultInventoryService {
         })
     }
 
+    #[tracing::instrument(skip_all, fields(
+        tenant_id = self.tenant_id.map(tracing::field::display),
+        product_id = %_product_id
+    ))]
     async fn update_standard_costs(&self, _product_id: Uuid, _new_cost: f64) -> Result<()> {
+        let _latency = self.latency.start("inventory.write", "update_standard_costs");
         // Implementation would update standard costs and create variance records
         Ok(())
     }
 
+    #[tracing::instrument(skip_all, fields(
+        tenant_id = self.tenant_id.map(tracing::field::display),
+        location_id = %location_id
+    ))]
     async fn get_cost_variance_analysis(&self, location_id: Uuid, _period_days: i32) -> Result<CostVarianceAnalysis> {
+        let _latency = self.latency.start("inventory.analytics", "get_cost_variance_analysis");
         Ok(CostVarianceAnalysis {
             location_id,
             period_start: Utc::now() - Duration::days(30),
@@ -865,20 +1127,46 @@ impl InventoryService for DefaultInventoryService {
         })
     }
 
+    #[tracing::instrument(skip_all, fields(
+        tenant_id = self.tenant_id.map(tracing::field::display),
+        location_id = location_id.map(tracing::field::display)
+    ))]
     async fn generate_inventory_alerts(&self, location_id: Option<Uuid>) -> Result<Vec<InventoryAlert>> {
-        self.repository.get_active_alerts(location_id, None).await
+        let _latency = self.latency.start("inventory.analytics", "generate_inventory_alerts");
+        self.repository.get_active_alerts(location_id, None).instrument(spans::repository("get_active_alerts")).await
     }
 
+    #[tracing::instrument(skip_all, fields(
+        tenant_id = self.tenant_id.map(tracing::field::display),
+        alert_id = %alert_id
+    ))]
     async fn acknowledge_alert(&self, alert_id: Uuid, acknowledged_by: Uuid) -> Result<InventoryAlert> {
-        self.repository.acknowledge_alert(alert_id, acknowledged_by).await
+        let _latency = self.latency.start("inventory.write", "acknowledge_alert");
+        self.repository.acknowledge_alert(alert_id, acknowledged_by)
+            .instrument(spans::repository("acknowledge_alert"))
+            .await
     }
 
+    #[tracing::instrument(skip_all, fields(
+        tenant_id = self.tenant_id.map(tracing::field::display),
+        location_id = location_id.map(tracing::field::display)
+    ))]
     async fn get_inventory_dashboard(&self, location_id: Option<Uuid>) -> Result<InventoryDashboard> {
-        self.repository.get_inventory_dashboard(location_id).await
+        let _latency = self.latency.start("inventory.analytics", "get_inventory_dashboard");
+        self.repository.get_inventory_dashboard(location_id)
+            .instrument(spans::repository("get_inventory_dashboard"))
+            .await
     }
 
+    #[tracing::instrument(skip_all, fields(
+        tenant_id = self.tenant_id.map(tracing::field::display),
+        location_id = location_id.map(tracing::field::display)
+    ))]
     async fn analyze_turnover_rates(&self, location_id: Option<Uuid>, period_days: i32) -> Result<Vec<TurnoverAnalysis>> {
-        let turnover_items = self.repository.get_turnover_analysis(location_id, period_days).await?;
+        let _latency = self.latency.start("inventory.analytics", "analyze_turnover_rates");
+        let turnover_items = self.repository.get_turnover_analysis(location_id, period_days)
+            .instrument(spans::repository("get_turnover_analysis"))
+            .await?;
 
         turnover_items.into_iter().map(|item| {
             let velocity_classification = match item.turnover_ratio {
@@ -910,16 +1198,35 @@ impl InventoryService for DefaultInventoryService {
         }).collect()
     }
 
+    #[tracing::instrument(skip_all, fields(
+        tenant_id = self.tenant_id.map(tracing::field::display),
+        product_id = %product_id,
+        location_id = %location_id
+    ))]
     async fn forecast_demand(&self, product_id: Uuid, location_id: Uuid, forecast_days: i32) -> Result<Vec<InventoryForecast>> {
-        self.repository.get_demand_forecast(product_id, location_id, forecast_days).await
+        let _latency = self.latency.start("inventory.analytics", "forecast_demand");
+        self.repository.get_demand_forecast(product_id, location_id, forecast_days)
+            .instrument(spans::repository("get_demand_forecast"))
+            .await
     }
 
+    #[tracing::instrument(skip_all, fields(
+        tenant_id = self.tenant_id.map(tracing::field::display),
+        location_id = %location_id
+    ))]
     async fn optimize_stock_levels(&self, location_id: Uuid) -> Result<Vec<InventoryOptimization>> {
+        let _latency = self.latency.start("inventory.analytics", "optimize_stock_levels");
         // Implementation would analyze current vs optimal stock levels
         Ok(vec![])
     }
 
+    #[tracing::instrument(skip_all, fields(
+        tenant_id = self.tenant_id.map(tracing::field::display),
+        product_id = %_product_id,
+        location_id = %_location_id
+    ))]
     async fn analyze_seasonal_patterns(&self, _product_id: Uuid, _location_id: Uuid) -> Result<SeasonalAnalysis> {
+        let _latency = self.latency.start("inventory.analytics", "analyze_seasonal_patterns");
         // Implementation would analyze historical patterns
         Ok(SeasonalAnalysis {
             product_id: _product_id,
@@ -932,21 +1239,2028 @@ impl InventoryService for DefaultInventoryService {
         })
     }
 
+    #[tracing::instrument(skip_all, fields(
+        tenant_id = self.tenant_id.map(tracing::field::display),
+        location_id = location_id.map(tracing::field::display)
+    ))]
     async fn calculate_inventory_kpis(&self, location_id: Option<Uuid>, period_start: DateTime<Utc>, period_end: DateTime<Utc>) -> Result<InventoryKPI> {
-        self.repository.calculate_inventory_kpis(location_id, period_start, period_end).await
+        let _latency = self.latency.start("inventory.analytics", "calculate_inventory_kpis");
+        self.repository.calculate_inventory_kpis(location_id, period_start, period_end)
+            .instrument(spans::repository("calculate_inventory_kpis"))
+            .await
     }
 
+    #[tracing::instrument(skip_all, fields(
+        tenant_id = self.tenant_id.map(tracing::field::display),
+        location_id = %location_id
+    ))]
     async fn generate_stock_aging_report(&self, location_id: Uuid) -> Result<Vec<StockAgingItem>> {
-        self.repository.get_stock_aging_report(location_id).await
+        let _latency = self.latency.start("inventory.analytics", "generate_stock_aging_report");
+        self.repository.get_stock_aging_report(location_id)
+            .instrument(spans::repository("get_stock_aging_report"))
+            .await
     }
 
+    #[tracing::instrument(skip_all, fields(
+        tenant_id = self.tenant_id.map(tracing::field::display),
+        location_id = %location_id
+    ))]
     async fn get_slow_moving_items(&self, location_id: Uuid, days_threshold: i32) -> Result<Vec<SlowMovingItem>> {
+        let _latency = self.latency.start("inventory.read", "get_slow_moving_items");
         // Implementation would identify slow-moving items
         Ok(vec![])
     }
 
+    #[tracing::instrument(skip_all, fields(
+        tenant_id = self.tenant_id.map(tracing::field::display),
+        location_id = %location_id
+    ))]
     async fn get_excess_stock_report(&self, location_id: Uuid) -> Result<Vec<ExcessStockItem>> {
+        let _latency = self.latency.start("inventory.analytics", "get_excess_stock_report");
         // Implementation would identify excess stock
         Ok(vec![])
     }
-}
\ No newline at end of file
+}
+/// Receive serialized units into a location
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReceiveSerialsRequest {
+    pub product_id: Uuid,
+    pub location_id: Uuid,
+    pub bin_location: Option<String>,
+    pub quantity: i32,
+    pub serial_numbers: Vec<String>,
+    pub unit_cost: Option<Decimal>,
+    pub reference_number: Option<String>,
+}
+
+/// Reserve or ship serialized units.
+///
+/// Serials must be listed explicitly unless `auto_pick` is set, in which case
+/// the oldest in-stock units are picked (FIFO).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutboundSerialsRequest {
+    pub product_id: Uuid,
+    pub location_id: Uuid,
+    pub quantity: i32,
+    #[serde(default)]
+    pub serial_numbers: Vec<String>,
+    #[serde(default)]
+    pub auto_pick: bool,
+    pub reference_number: Option<String>,
+}
+
+/// Book shipped units back in as returns
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReturnSerialsRequest {
+    pub product_id: Uuid,
+    pub location_id: Uuid,
+    pub bin_location: Option<String>,
+    pub serial_numbers: Vec<String>,
+    pub reference_number: Option<String>,
+}
+
+/// Move in-stock units and their quantity between locations in one step
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferSerialsRequest {
+    pub product_id: Uuid,
+    pub from_location_id: Uuid,
+    pub to_location_id: Uuid,
+    pub to_bin_location: Option<String>,
+    pub quantity: i32,
+    #[serde(default)]
+    pub serial_numbers: Vec<String>,
+    #[serde(default)]
+    pub auto_pick: bool,
+    pub transfer_id: Option<Uuid>,
+}
+
+/// Serial number tracking for products flagged `is_serialized`
+#[async_trait]
+pub trait SerialTrackingService: Send + Sync {
+    async fn receive_serials(&self, request: ReceiveSerialsRequest) -> Result<Vec<SerialUnit>>;
+    async fn reserve_serials(&self, request: OutboundSerialsRequest) -> Result<Vec<SerialUnit>>;
+    async fn release_serials(&self, product_id: Uuid, serial_numbers: Vec<String>) -> Result<Vec<SerialUnit>>;
+    async fn ship_serials(&self, request: OutboundSerialsRequest) -> Result<Vec<SerialUnit>>;
+    async fn return_serials(&self, request: ReturnSerialsRequest) -> Result<Vec<SerialUnit>>;
+    async fn restock_serials(&self, product_id: Uuid, serial_numbers: Vec<String>) -> Result<Vec<SerialUnit>>;
+    async fn scrap_serials(&self, product_id: Uuid, serial_numbers: Vec<String>, reason: String) -> Result<Vec<SerialUnit>>;
+    async fn transfer_serials(&self, request: TransferSerialsRequest) -> Result<Vec<SerialUnit>>;
+    async fn get_serial_history(&self, serial_number: &str) -> Result<SerialHistory>;
+    async fn list_product_serials(
+        &self,
+        product_id: Uuid,
+        location_id: Option<Uuid>,
+        status: Option<SerialStatus>,
+    ) -> Result<Vec<SerialUnit>>;
+}
+
+pub struct DefaultSerialTrackingService {
+    repository: Arc<dyn SerialUnitRepository>,
+    products: Arc<dyn ProductRepository>,
+    tenant_context: TenantContext,
+}
+
+impl DefaultSerialTrackingService {
+    pub fn new(
+        repository: Arc<dyn SerialUnitRepository>,
+        products: Arc<dyn ProductRepository>,
+        tenant_context: TenantContext,
+    ) -> Self {
+        Self {
+            repository,
+            products,
+            tenant_context,
+        }
+    }
+
+    /// Serial operations are only allowed for products flagged `is_serialized`
+    async fn ensure_serialized(&self, product_id: Uuid) -> Result<()> {
+        let product = self
+            .products
+            .get_product_by_id(self.tenant_context.tenant_id, product_id)
+            .await?
+            .ok_or_else(|| MasterDataError::ProductNotFound { id: product_id.to_string() })?;
+
+        if !product.is_serialized {
+            return Err(MasterDataError::ValidationError {
+                field: "product_id".to_string(),
+                message: "Product is not serialized".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Load explicitly named units of a product
+    async fn load_units(&self, product_id: Uuid, serial_numbers: &[String]) -> Result<Vec<SerialUnit>> {
+        let mut units = Vec::new();
+        for serial in normalize_serials(serial_numbers)? {
+            let unit = self
+                .repository
+                .get_serial_unit(self.tenant_context.tenant_id, &serial)
+                .await?
+                .filter(|unit| unit.product_id == product_id)
+                .ok_or(MasterDataError::SerialNotFound { serial })?;
+            units.push(unit);
+        }
+        Ok(units)
+    }
+
+    async fn units_at(&self, product_id: Uuid, location_id: Uuid) -> Result<Vec<SerialUnit>> {
+        self.repository
+            .list_serial_units(self.tenant_context.tenant_id, product_id, Some(location_id), None)
+            .await
+    }
+
+    fn movement(
+        &self,
+        product_id: Uuid,
+        location_id: Uuid,
+        movement_type: &str,
+        quantity: i32,
+        units: &[SerialUnit],
+        reference_number: Option<String>,
+    ) -> InventoryMovement {
+        let now = Utc::now();
+        InventoryMovement {
+            id: Some(Uuid::new_v4()),
+            product_id: Some(product_id),
+            location_id: Some(location_id),
+            movement_type: Some(movement_type.to_string()),
+            quantity: Some(quantity),
+            unit_cost: None,
+            reference_document: None,
+            reference_number,
+            reason: None,
+            batch_number: None,
+            serial_numbers: Some(units.iter().map(|unit| unit.serial_number.clone()).collect()),
+            expiry_date: None,
+            operator_id: Some(self.tenant_context.user_id),
+            operator_name: None,
+            created_at: Some(now),
+            effective_date: Some(now),
+            audit_trail: None,
+        }
+    }
+
+    fn event_context(&self, movement_id: Option<Uuid>, reference: Option<String>) -> SerialEventContext {
+        SerialEventContext {
+            tenant_id: self.tenant_context.tenant_id,
+            movement_id,
+            reference,
+            performed_by: self.tenant_context.user_id,
+            occurred_at: Utc::now(),
+        }
+    }
+
+    /// Apply a status change to every unit and persist it with `movements`
+    async fn transition_units(
+        &self,
+        mut units: Vec<SerialUnit>,
+        next: SerialStatus,
+        location_id: Option<Uuid>,
+        movements: Vec<InventoryMovement>,
+        reservations: Vec<ReservationChange>,
+        reference: Option<String>,
+    ) -> Result<Vec<SerialUnit>> {
+        let context = self.event_context(movements.first().and_then(|m| m.id), reference);
+        let events = units
+            .iter_mut()
+            .map(|unit| unit.transition(next, location_id, &context))
+            .collect::<Result<Vec<_>>>()?;
+
+        self.repository
+            .apply_serial_changes(SerialChangeSet {
+                movements,
+                received: Vec::new(),
+                updated: units.clone(),
+                events,
+                reservations,
+            })
+            .await?;
+        Ok(units)
+    }
+
+    /// Reserved stock the units hold, per location, to release (negative) or reserve (positive)
+    fn reservations(product_id: Uuid, units: &[SerialUnit], sign: i32) -> Vec<ReservationChange> {
+        let mut by_location: HashMap<Uuid, i32> = HashMap::new();
+        for location_id in units.iter().filter_map(|unit| unit.location_id) {
+            *by_location.entry(location_id).or_default() += sign;
+        }
+        by_location
+            .into_iter()
+            .map(|(location_id, quantity)| ReservationChange { product_id, location_id, quantity })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl SerialTrackingService for DefaultSerialTrackingService {
+    async fn receive_serials(&self, request: ReceiveSerialsRequest) -> Result<Vec<SerialUnit>> {
+        self.ensure_serialized(request.product_id).await?;
+        let serials = validate_inbound_serials(request.quantity, &request.serial_numbers)?;
+        let existing = self
+            .repository
+            .find_existing_serials(self.tenant_context.tenant_id, &serials)
+            .await?;
+        ensure_serials_are_new(&serials, &existing)?;
+
+        let movement_id = Uuid::new_v4();
+        let context = self.event_context(Some(movement_id), request.reference_number.clone());
+        let (units, events): (Vec<_>, Vec<_>) = serials
+            .into_iter()
+            .map(|serial| {
+                SerialUnit::receive(
+                    request.product_id,
+                    serial,
+                    request.location_id,
+                    request.bin_location.clone(),
+                    movement_id,
+                    &context,
+                )
+            })
+            .unzip();
+
+        let mut movement = self.movement(
+            request.product_id,
+            request.location_id,
+            "receipt",
+            request.quantity,
+            &units,
+            request.reference_number,
+        );
+        movement.id = Some(movement_id);
+        movement.unit_cost = request.unit_cost;
+
+        self.repository
+            .apply_serial_changes(SerialChangeSet {
+                movements: vec![movement],
+                received: units.clone(),
+                updated: Vec::new(),
+                events,
+                reservations: Vec::new(),
+            })
+            .await?;
+        Ok(units)
+    }
+
+    async fn reserve_serials(&self, request: OutboundSerialsRequest) -> Result<Vec<SerialUnit>> {
+        self.ensure_serialized(request.product_id).await?;
+        let in_stock: Vec<SerialUnit> = self
+            .units_at(request.product_id, request.location_id)
+            .await?
+            .into_iter()
+            .filter(|unit| unit.status == SerialStatus::InStock)
+            .collect();
+        let units = select_serials(&in_stock, request.quantity, &request.serial_numbers, request.auto_pick)?;
+
+        let reservations = Self::reservations(request.product_id, &units, 1);
+        self.transition_units(units, SerialStatus::Reserved, None, Vec::new(), reservations, request.reference_number)
+            .await
+    }
+
+    async fn release_serials(&self, product_id: Uuid, serial_numbers: Vec<String>) -> Result<Vec<SerialUnit>> {
+        self.ensure_serialized(product_id).await?;
+        let units = self.load_units(product_id, &serial_numbers).await?;
+        if let Some(unit) = units.iter().find(|unit| unit.status != SerialStatus::Reserved) {
+            return Err(MasterDataError::InvalidSerialTransition {
+                serial: unit.serial_number.clone(),
+                from: unit.status.as_str().to_string(),
+                to: "released".to_string(),
+            });
+        }
+
+        let reservations = Self::reservations(product_id, &units, -1);
+        self.transition_units(units, SerialStatus::InStock, None, Vec::new(), reservations, None).await
+    }
+
+    async fn ship_serials(&self, request: OutboundSerialsRequest) -> Result<Vec<SerialUnit>> {
+        self.ensure_serialized(request.product_id).await?;
+        // Explicit picks may ship reserved units; auto-pick never takes someone else's reservation
+        let candidates: Vec<SerialUnit> = self
+            .units_at(request.product_id, request.location_id)
+            .await?
+            .into_iter()
+            .filter(|unit| match unit.status {
+                SerialStatus::InStock => true,
+                SerialStatus::Reserved => !request.serial_numbers.is_empty(),
+                _ => false,
+            })
+            .collect();
+        let units = select_serials(&candidates, request.quantity, &request.serial_numbers, request.auto_pick)?;
+
+        let movement = self.movement(
+            request.product_id,
+            request.location_id,
+            "shipment",
+            -request.quantity,
+            &units,
+            request.reference_number.clone(),
+        );
+        let reserved: Vec<SerialUnit> = units.iter().filter(|unit| unit.status == SerialStatus::Reserved).cloned().collect();
+        let reservations = Self::reservations(request.product_id, &reserved, -1);
+        self.transition_units(units, SerialStatus::Shipped, None, vec![movement], reservations, request.reference_number)
+            .await
+    }
+
+    async fn return_serials(&self, request: ReturnSerialsRequest) -> Result<Vec<SerialUnit>> {
+        self.ensure_serialized(request.product_id).await?;
+        let mut units = self.load_units(request.product_id, &request.serial_numbers).await?;
+
+        let movement = self.movement(
+            request.product_id,
+            request.location_id,
+            "return",
+            units.len() as i32,
+            &units,
+            request.reference_number.clone(),
+        );
+        let context = self.event_context(movement.id, request.reference_number);
+        let mut events = Vec::with_capacity(units.len());
+        for unit in &mut units {
+            events.push(unit.transition(SerialStatus::Returned, Some(request.location_id), &context)?);
+            unit.bin_location = request.bin_location.clone();
+        }
+
+        self.repository
+            .apply_serial_changes(SerialChangeSet {
+                movements: vec![movement],
+                received: Vec::new(),
+                updated: units.clone(),
+                events,
+                reservations: Vec::new(),
+            })
+            .await?;
+        Ok(units)
+    }
+
+    async fn restock_serials(&self, product_id: Uuid, serial_numbers: Vec<String>) -> Result<Vec<SerialUnit>> {
+        self.ensure_serialized(product_id).await?;
+        let units = self.load_units(product_id, &serial_numbers).await?;
+        if let Some(unit) = units.iter().find(|unit| unit.status != SerialStatus::Returned) {
+            return Err(MasterDataError::InvalidSerialTransition {
+                serial: unit.serial_number.clone(),
+                from: unit.status.as_str().to_string(),
+                to: "restocked".to_string(),
+            });
+        }
+
+        self.transition_units(units, SerialStatus::InStock, None, Vec::new(), Vec::new(), None).await
+    }
+
+    async fn scrap_serials(&self, product_id: Uuid, serial_numbers: Vec<String>, reason: String) -> Result<Vec<SerialUnit>> {
+        self.ensure_serialized(product_id).await?;
+        let units = self.load_units(product_id, &serial_numbers).await?;
+
+        // Scrapped units leave on-hand stock at whatever location they sit in
+        let mut by_location: HashMap<Uuid, Vec<SerialUnit>> = HashMap::new();
+        for unit in &units {
+            if let (true, Some(location_id)) = (unit.status.is_on_hand(), unit.location_id) {
+                by_location.entry(location_id).or_default().push(unit.clone());
+            }
+        }
+        let movements = by_location
+            .into_iter()
+            .map(|(location_id, located)| {
+                let mut movement = self.movement(product_id, location_id, "damage", -(located.len() as i32), &located, None);
+                movement.reason = Some(reason.clone());
+                movement
+            })
+            .collect();
+
+        self.transition_units(units, SerialStatus::Scrapped, None, movements, Vec::new(), Some(reason))
+            .await
+    }
+
+    async fn transfer_serials(&self, request: TransferSerialsRequest) -> Result<Vec<SerialUnit>> {
+        if request.from_location_id == request.to_location_id {
+            return Err(MasterDataError::ValidationError { field: "location".to_string(), message: "Cannot transfer to the same location".to_string() });
+        }
+        self.ensure_serialized(request.product_id).await?;
+
+        let in_stock: Vec<SerialUnit> = self
+            .units_at(request.product_id, request.from_location_id)
+            .await?
+            .into_iter()
+            .filter(|unit| unit.status == SerialStatus::InStock)
+            .collect();
+        let mut units = select_serials(&in_stock, request.quantity, &request.serial_numbers, request.auto_pick)?;
+
+        let reference = request.transfer_id.map(|id| id.to_string());
+        let outbound = self.movement(
+            request.product_id,
+            request.from_location_id,
+            "transfer",
+            -request.quantity,
+            &units,
+            reference.clone(),
+        );
+        let inbound = self.movement(
+            request.product_id,
+            request.to_location_id,
+            "transfer",
+            request.quantity,
+            &units,
+            reference.clone(),
+        );
+
+        let context = self.event_context(outbound.id, reference);
+        let events = units
+            .iter_mut()
+            .map(|unit| unit.relocate(request.to_location_id, request.to_bin_location.clone(), &context))
+            .collect::<Result<Vec<_>>>()?;
+
+        self.repository
+            .apply_serial_changes(SerialChangeSet {
+                movements: vec![outbound, inbound],
+                received: Vec::new(),
+                updated: units.clone(),
+                events,
+                reservations: Vec::new(),
+            })
+            .await?;
+        Ok(units)
+    }
+
+    async fn get_serial_history(&self, serial_number: &str) -> Result<SerialHistory> {
+        let unit = self
+            .repository
+            .get_serial_unit(self.tenant_context.tenant_id, serial_number.trim())
+            .await?
+            .ok_or_else(|| MasterDataError::SerialNotFound { serial: serial_number.to_string() })?;
+        let events = self
+            .repository
+            .get_serial_events(self.tenant_context.tenant_id, unit.id)
+            .await?;
+
+        Ok(SerialHistory { unit, events })
+    }
+
+    async fn list_product_serials(
+        &self,
+        product_id: Uuid,
+        location_id: Option<Uuid>,
+        status: Option<SerialStatus>,
+    ) -> Result<Vec<SerialUnit>> {
+        self.ensure_serialized(product_id).await?;
+        self.repository
+            .list_serial_units(self.tenant_context.tenant_id, product_id, location_id, status)
+            .await
+    }
+}
+
+/// Physical stocktakes imported from scanner files
+#[async_trait]
+pub trait StocktakeService: Send + Sync {
+    async fn create_session(&self, request: CreateStocktakeSessionRequest, created_by: Uuid) -> Result<StocktakeSession>;
+    /// Import or re-import one scanner file and recompute the session's variances
+    async fn import_file(&self, session_id: Uuid, file_name: &str, content: &str) -> Result<StocktakeImportResult>;
+    async fn get_session(&self, session_id: Uuid) -> Result<StocktakeSession>;
+    /// Hand the reviewed variances to cycle-count approval, one count per location
+    async fn submit_session(&self, session_id: Uuid, submitted_by: Uuid) -> Result<StocktakeSession>;
+    async fn get_shrinkage_summary(&self, session_id: Uuid) -> Result<Vec<LocationShrinkage>>;
+}
+
+pub struct DefaultStocktakeService {
+    repository: Arc<dyn StocktakeRepository>,
+    inventory: Arc<dyn InventoryRepository>,
+    tenant_context: TenantContext,
+}
+
+impl DefaultStocktakeService {
+    pub fn new(
+        repository: Arc<dyn StocktakeRepository>,
+        inventory: Arc<dyn InventoryRepository>,
+        tenant_context: TenantContext,
+    ) -> Self {
+        Self {
+            repository,
+            inventory,
+            tenant_context,
+        }
+    }
+
+    /// Rebuild the session's lines from every stored scan
+    async fn recompute(&self, session: &mut StocktakeSession) -> Result<()> {
+        let tenant_id = self.tenant_context.tenant_id;
+        let scans = self.repository.get_scan_lines(tenant_id, session.id).await?;
+
+        let mut barcodes: Vec<String> = scans.iter().map(|scan| scan.barcode.clone()).collect();
+        barcodes.sort();
+        barcodes.dedup();
+        let mut codes: Vec<String> = scans.iter().map(|scan| scan.location_code.clone()).collect();
+        codes.sort();
+        codes.dedup();
+
+        let products = self.repository.find_products_by_barcode(tenant_id, &barcodes).await?;
+        let locations = self.repository.find_locations_by_code(tenant_id, &codes).await?;
+        let aggregation = stocktake::aggregate_scans(&scans, &products, &locations);
+
+        let pairs: Vec<(Uuid, Uuid)> = aggregation
+            .items
+            .iter()
+            .map(|item| (item.product_id, item.location_id))
+            .collect();
+        let positions = if pairs.is_empty() {
+            HashMap::new()
+        } else {
+            self.repository.get_stock_positions(&pairs, session.snapshot_at).await?
+        };
+
+        session.lines = stocktake::compute_variances(&aggregation.items, &positions);
+        session.unmatched_barcodes = aggregation.unmatched_barcodes;
+        session.unknown_locations = aggregation.unknown_locations;
+        session.updated_at = Utc::now();
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl StocktakeService for DefaultStocktakeService {
+    async fn create_session(&self, request: CreateStocktakeSessionRequest, created_by: Uuid) -> Result<StocktakeSession> {
+        let name = request.name.trim().to_string();
+        if name.is_empty() {
+            return Err(MasterDataError::ValidationError {
+                field: "name".to_string(),
+                message: "Session name is required".to_string(),
+            });
+        }
+        let now = Utc::now();
+        if request.snapshot_at > now {
+            return Err(MasterDataError::ValidationError {
+                field: "snapshot_at".to_string(),
+                message: "Snapshot time cannot be in the future".to_string(),
+            });
+        }
+
+        let session = StocktakeSession {
+            id: Uuid::new_v4(),
+            tenant_id: self.tenant_context.tenant_id,
+            name,
+            snapshot_at: request.snapshot_at,
+            status: StocktakeStatus::Open,
+            lines: Vec::new(),
+            unmatched_barcodes: Vec::new(),
+            unknown_locations: Vec::new(),
+            cycle_count_ids: HashMap::new(),
+            created_by,
+            created_at: now,
+            updated_at: now,
+            submitted_by: None,
+            submitted_at: None,
+        };
+
+        self.repository.create_session(&session).await?;
+        Ok(session)
+    }
+
+    async fn import_file(&self, session_id: Uuid, file_name: &str, content: &str) -> Result<StocktakeImportResult> {
+        let mut session = self.get_session(session_id).await?;
+        stocktake::ensure_open(&session)?;
+
+        let file_name = file_name.trim();
+        if file_name.is_empty() {
+            return Err(MasterDataError::ValidationError {
+                field: "file_name".to_string(),
+                message: "File name is required".to_string(),
+            });
+        }
+
+        let parsed = stocktake::parse_scan_file(file_name, content);
+        if parsed.lines.is_empty() && parsed.errors.is_empty() {
+            return Err(MasterDataError::ValidationError {
+                field: "file".to_string(),
+                message: "File contains no scan lines".to_string(),
+            });
+        }
+
+        let changes = self
+            .repository
+            .replace_file_lines(self.tenant_context.tenant_id, session_id, file_name, &parsed.lines)
+            .await?;
+
+        self.recompute(&mut session).await?;
+        self.repository.update_session(&session).await?;
+
+        Ok(StocktakeImportResult {
+            session,
+            file_name: file_name.to_string(),
+            changes,
+            invalid_lines: parsed.errors,
+        })
+    }
+
+    async fn get_session(&self, session_id: Uuid) -> Result<StocktakeSession> {
+        self.repository
+            .get_session(self.tenant_context.tenant_id, session_id)
+            .await?
+            .ok_or_else(|| MasterDataError::NotFoundError(format!("Stocktake session {}", session_id)))
+    }
+
+    async fn submit_session(&self, session_id: Uuid, submitted_by: Uuid) -> Result<StocktakeSession> {
+        let mut session = self.get_session(session_id).await?;
+        stocktake::ensure_open(&session)?;
+        if session.lines.is_empty() {
+            return Err(MasterDataError::ValidationError {
+                field: "session_id".to_string(),
+                message: "Stocktake session has no matched scan lines".to_string(),
+            });
+        }
+
+        // Pick up movements booked since the last upload before handing off
+        self.recompute(&mut session).await?;
+
+        let now = Utc::now();
+        for count in stocktake::cycle_counts_for_session(&session, submitted_by, now) {
+            let count = self.inventory.create_cycle_count(count).await?;
+            session.cycle_count_ids.insert(count.location_id, count.id);
+        }
+
+        session.status = StocktakeStatus::Submitted;
+        session.submitted_by = Some(submitted_by);
+        session.submitted_at = Some(now);
+        session.updated_at = now;
+        self.repository.update_session(&session).await?;
+
+        Ok(session)
+    }
+
+    async fn get_shrinkage_summary(&self, session_id: Uuid) -> Result<Vec<LocationShrinkage>> {
+        let session = self.get_session(session_id).await?;
+        Ok(stocktake::shrinkage_by_location(&session.lines))
+    }
+}
+
+/// Customer return orders: quarantine receipt and inspection dispositions
+#[async_trait]
+pub trait ReturnsService: Send + Sync {
+    async fn create_return(&self, request: CreateReturnOrderRequest, created_by: Uuid) -> Result<ReturnOrder>;
+    async fn get_return(&self, return_id: Uuid) -> Result<ReturnOrder>;
+    async fn list_returns(&self, status: Option<ReturnStatus>, customer_id: Option<Uuid>) -> Result<Vec<ReturnOrder>>;
+    /// Book the returned goods into a quarantine location
+    async fn receive_return(&self, return_id: Uuid, request: ReceiveReturnRequest, received_by: Uuid) -> Result<ReturnOrder>;
+    /// Record dispositions for some or all lines and move the units out of quarantine
+    async fn inspect_return(&self, return_id: Uuid, request: InspectReturnRequest, inspected_by: Uuid) -> Result<ReturnOrder>;
+    async fn cancel_return(&self, return_id: Uuid) -> Result<ReturnOrder>;
+    /// Returned units relative to shipped units per product, highest rate first
+    async fn get_return_rates(&self, period_start: DateTime<Utc>, period_end: DateTime<Utc>) -> Result<Vec<ProductReturnRate>>;
+}
+
+pub struct DefaultReturnsService {
+    repository: Arc<dyn ReturnOrderRepository>,
+    tenant_context: TenantContext,
+    quarantine_location_types: Vec<String>,
+}
+
+impl DefaultReturnsService {
+    pub fn new(
+        repository: Arc<dyn ReturnOrderRepository>,
+        tenant_context: TenantContext,
+        quarantine_location_types: Vec<String>,
+    ) -> Self {
+        Self {
+            repository,
+            tenant_context,
+            quarantine_location_types,
+        }
+    }
+
+    fn is_quarantine(&self, location_type: &str) -> bool {
+        self.quarantine_location_types.iter().any(|quarantine| quarantine == location_type)
+    }
+
+    /// Check that every location exists and is (or is not) a quarantine location
+    async fn check_location_types(&self, expectations: &[(&str, Uuid, bool)]) -> Result<()> {
+        if expectations.is_empty() {
+            return Ok(());
+        }
+        let ids: Vec<Uuid> = expectations.iter().map(|(_, id, _)| *id).collect();
+        let types = self.repository.get_location_types(self.tenant_context.tenant_id, &ids).await?;
+
+        for (field, location_id, quarantine) in expectations {
+            let location_type = types
+                .get(location_id)
+                .ok_or_else(|| MasterDataError::NotFoundError(format!("Location {}", location_id)))?;
+            if self.is_quarantine(location_type) != *quarantine {
+                let expected = if *quarantine { "a quarantine" } else { "a sellable, non-quarantine" };
+                return Err(MasterDataError::ValidationError {
+                    field: field.to_string(),
+                    message: format!("Location {} ({}) is not {} location", location_id, location_type, expected),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ReturnsService for DefaultReturnsService {
+    async fn create_return(&self, request: CreateReturnOrderRequest, created_by: Uuid) -> Result<ReturnOrder> {
+        let order = ReturnOrder::new(self.tenant_context.tenant_id, request, created_by, Utc::now())?;
+        self.repository.create_return_order(&order).await?;
+        Ok(order)
+    }
+
+    async fn get_return(&self, return_id: Uuid) -> Result<ReturnOrder> {
+        self.repository
+            .get_return_order(self.tenant_context.tenant_id, return_id)
+            .await?
+            .ok_or_else(|| MasterDataError::NotFoundError(format!("Return order {}", return_id)))
+    }
+
+    async fn list_returns(&self, status: Option<ReturnStatus>, customer_id: Option<Uuid>) -> Result<Vec<ReturnOrder>> {
+        self.repository
+            .list_return_orders(self.tenant_context.tenant_id, status, customer_id)
+            .await
+    }
+
+    async fn receive_return(&self, return_id: Uuid, request: ReceiveReturnRequest, received_by: Uuid) -> Result<ReturnOrder> {
+        let mut order = self.get_return(return_id).await?;
+        self.check_location_types(&[("quarantine_location_id", request.quarantine_location_id, true)])
+            .await?;
+
+        let expected_status = order.status;
+        let movements = order.receive(request.quarantine_location_id, received_by, Utc::now())?;
+        self.repository
+            .apply_return_change(ReturnOrderChange {
+                order: order.clone(),
+                expected_status,
+                movements,
+            })
+            .await?;
+        Ok(order)
+    }
+
+    async fn inspect_return(&self, return_id: Uuid, request: InspectReturnRequest, inspected_by: Uuid) -> Result<ReturnOrder> {
+        let mut order = self.get_return(return_id).await?;
+
+        // Restocked units must land in sellable stock; refurbishment happens in
+        // another non-sellable area until the units are restocked separately
+        let expectations: Vec<(&str, Uuid, bool)> = request
+            .lines
+            .iter()
+            .filter_map(|inspection| match (inspection.disposition, inspection.target_location_id) {
+                (Disposition::Restock, Some(target)) => Some(("target_location_id", target, false)),
+                (Disposition::Refurbish, Some(target)) => Some(("target_location_id", target, true)),
+                _ => None,
+            })
+            .collect();
+        self.check_location_types(&expectations).await?;
+
+        let expected_status = order.status;
+        let movements = order.inspect(&request, inspected_by, Utc::now())?;
+        self.repository
+            .apply_return_change(ReturnOrderChange {
+                order: order.clone(),
+                expected_status,
+                movements,
+            })
+            .await?;
+        Ok(order)
+    }
+
+    async fn cancel_return(&self, return_id: Uuid) -> Result<ReturnOrder> {
+        let mut order = self.get_return(return_id).await?;
+        let expected_status = order.status;
+        order.cancel(Utc::now())?;
+        self.repository
+            .apply_return_change(ReturnOrderChange {
+                order: order.clone(),
+                expected_status,
+                movements: Vec::new(),
+            })
+            .await?;
+        Ok(order)
+    }
+
+    async fn get_return_rates(&self, period_start: DateTime<Utc>, period_end: DateTime<Utc>) -> Result<Vec<ProductReturnRate>> {
+        if period_start >= period_end {
+            return Err(MasterDataError::ValidationError {
+                field: "period_start".to_string(),
+                message: "Period start must be before period end".to_string(),
+            });
+        }
+        let returned = self
+            .repository
+            .get_returned_quantities(self.tenant_context.tenant_id, period_start, period_end)
+            .await?;
+        let mut product_ids: Vec<Uuid> = returned.iter().map(|(product_id, _, _)| *product_id).collect();
+        product_ids.sort();
+        product_ids.dedup();
+        let shipped = self
+            .repository
+            .get_shipped_quantities(&product_ids, period_start, period_end)
+            .await?;
+        Ok(returns::compute_return_rates(&returned, &shipped))
+    }
+}
+
+/// What-if demand scenarios on top of the regular forecast
+#[async_trait]
+pub trait ForecastScenarioService: Send + Sync {
+    /// Forecast, apply the uplifts and project stock; stored when `request.save` is set
+    async fn run_scenario(&self, request: ForecastScenarioRequest, created_by: Option<Uuid>) -> Result<ForecastScenario>;
+    async fn get_scenario(&self, scenario_id: Uuid) -> Result<ForecastScenario>;
+    async fn list_scenarios(&self) -> Result<Vec<ForecastScenario>>;
+    /// Projected against actual demand for the days of a saved scenario that have passed
+    async fn compare_with_actuals(&self, scenario_id: Uuid) -> Result<ForecastScenarioComparison>;
+}
+
+/// Saved scenarios listed per tenant
+const SCENARIO_LIST_LIMIT: i64 = 100;
+
+pub struct DefaultForecastScenarioService {
+    repository: Arc<dyn ForecastScenarioRepository>,
+    inventory: Arc<dyn InventoryRepository>,
+    engine: Arc<dyn InventoryOptimizationEngine>,
+    tenant_context: TenantContext,
+}
+
+impl DefaultForecastScenarioService {
+    pub fn new(
+        repository: Arc<dyn ForecastScenarioRepository>,
+        inventory: Arc<dyn InventoryRepository>,
+        engine: Arc<dyn InventoryOptimizationEngine>,
+        tenant_context: TenantContext,
+    ) -> Self {
+        Self {
+            repository,
+            inventory,
+            engine,
+            tenant_context,
+        }
+    }
+
+    /// Baseline daily demand from tomorrow; no history means no baseline demand
+    async fn baseline_forecast(&self, item: ScenarioItem, horizon_days: i32) -> Result<(Vec<f64>, bool)> {
+        match self
+            .engine
+            .generate_demand_forecast(item.product_id, item.location_id, horizon_days)
+            .await
+        {
+            Ok(forecast) => Ok((forecast.daily_demand_forecast, true)),
+            Err(MasterDataError::NotFoundError(_)) => Ok((Vec::new(), false)),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+#[async_trait]
+impl ForecastScenarioService for DefaultForecastScenarioService {
+    async fn run_scenario(&self, request: ForecastScenarioRequest, created_by: Option<Uuid>) -> Result<ForecastScenario> {
+        let today = Utc::now().date_naive();
+        request.validate(today)?;
+        let horizon_days = request.horizon_days(today);
+
+        let mut items = request.items.clone();
+        items.sort_by_key(|item| (item.product_id, item.location_id));
+        items.dedup();
+        let pairs: Vec<(Uuid, Uuid)> = items.iter().map(|item| (item.product_id, item.location_id)).collect();
+
+        // Sellable stock not yet promised to orders; quarantine stock does not count
+        let opening: HashMap<(Uuid, Uuid), f64> = self
+            .inventory
+            .get_stock_availability(&pairs)
+            .await?
+            .iter()
+            .map(|stock| ((stock.product_id, stock.location_id), stock.available_to_promise() as f64))
+            .collect();
+        let mut supply: HashMap<(Uuid, Uuid), Vec<IncomingSupply>> = HashMap::new();
+        for incoming in self.repository.get_incoming_supply(&pairs).await? {
+            supply.entry((incoming.product_id, incoming.location_id)).or_default().push(incoming);
+        }
+
+        let mut results = Vec::with_capacity(items.len());
+        for item in items {
+            let key = (item.product_id, item.location_id);
+            let (daily_forecast, forecast_available) = self.baseline_forecast(item, horizon_days).await?;
+            let lead_time = self.engine.get_lead_time(item.product_id, item.location_id).await?;
+
+            let inputs = scenario::ItemInputs {
+                item,
+                opening_stock: opening.get(&key).copied().unwrap_or(0.0),
+                daily_forecast,
+                forecast_available,
+                supply: supply.remove(&key).unwrap_or_default(),
+                lead_time_days: lead_time.days,
+                lead_time_source: lead_time.source,
+            };
+            results.push(scenario::project_item(
+                &inputs,
+                &request.uplifts,
+                today,
+                request.start_date,
+                request.end_date,
+            ));
+        }
+
+        let scenario = ForecastScenario {
+            id: Uuid::new_v4(),
+            tenant_id: self.tenant_context.tenant_id,
+            name: request.name.trim().to_string(),
+            start_date: request.start_date,
+            end_date: request.end_date,
+            uplifts: request.uplifts,
+            items: results,
+            created_by,
+            created_at: Utc::now(),
+        };
+
+        if request.save {
+            self.repository.save_scenario(&scenario).await?;
+        }
+        Ok(scenario)
+    }
+
+    async fn get_scenario(&self, scenario_id: Uuid) -> Result<ForecastScenario> {
+        self.repository
+            .get_scenario(self.tenant_context.tenant_id, scenario_id)
+            .await?
+            .ok_or_else(|| MasterDataError::NotFoundError(format!("Forecast scenario {}", scenario_id)))
+    }
+
+    async fn list_scenarios(&self) -> Result<Vec<ForecastScenario>> {
+        self.repository
+            .list_scenarios(self.tenant_context.tenant_id, SCENARIO_LIST_LIMIT)
+            .await
+    }
+
+    async fn compare_with_actuals(&self, scenario_id: Uuid) -> Result<ForecastScenarioComparison> {
+        let scenario = self.get_scenario(scenario_id).await?;
+        // Today is not over yet
+        let compared_through = (Utc::now().date_naive() - Duration::days(1)).min(scenario.end_date);
+        let first_day = scenario.items.iter().filter_map(|item| item.days.first()).map(|day| day.date).min();
+
+        let mut items = Vec::with_capacity(scenario.items.len());
+        for item in &scenario.items {
+            let actual = match first_day {
+                Some(from) if from <= compared_through => {
+                    self.repository
+                        .get_daily_demand(item.product_id, item.location_id, from, compared_through)
+                        .await?
+                }
+                _ => HashMap::new(),
+            };
+            items.push(scenario::compare_with_actuals(item, &actual, compared_through));
+        }
+
+        Ok(ForecastScenarioComparison {
+            scenario_id: scenario.id,
+            name: scenario.name,
+            compared_through,
+            items,
+        })
+    }
+}
+
+/// Stock transfers between locations, held for approval where a tenant rule requires it
+#[async_trait]
+pub trait StockTransferService: Send + Sync {
+    /// Reserve the quantity at the source and start the transfer as `pending`, or
+    /// as `pending_approval` if it matches one of the tenant's approval rules
+    async fn create_transfer(&self, request: CreateStockTransferRequest) -> Result<TransferRecord>;
+    async fn get_transfer(&self, transfer_id: Uuid) -> Result<TransferRecord>;
+    /// With `awaiting_my_approval`, only transfers the acting user may decide
+    async fn list_transfers(&self, status: Option<TransferStatus>, awaiting_my_approval: bool) -> Result<Vec<TransferRecord>>;
+    /// Release a transfer waiting for approval into `pending`
+    async fn approve_transfer(&self, transfer_id: Uuid, comment: Option<String>) -> Result<TransferRecord>;
+    /// Reject a transfer waiting for approval and release its reserved stock
+    async fn reject_transfer(&self, transfer_id: Uuid, comment: Option<String>) -> Result<TransferRecord>;
+    async fn get_approval_rules(&self) -> Result<Vec<TransferApprovalRule>>;
+    async fn set_approval_rules(&self, rules: Vec<TransferApprovalRule>) -> Result<Vec<TransferApprovalRule>>;
+}
+
+pub struct DefaultStockTransferService {
+    repository: Arc<dyn StockTransferRepository>,
+    tenant_context: TenantContext,
+    capacity: Option<Arc<dyn LocationCapacityService>>,
+}
+
+impl DefaultStockTransferService {
+    pub fn new(repository: Arc<dyn StockTransferRepository>, tenant_context: TenantContext) -> Self {
+        Self {
+            repository,
+            tenant_context,
+            capacity: None,
+        }
+    }
+
+    /// Check new transfers against the capacity of the target location
+    pub fn with_capacity_checks(mut self, capacity: Arc<dyn LocationCapacityService>) -> Self {
+        self.capacity = Some(capacity);
+        self
+    }
+
+    fn require_permission(&self, permission: &str, action: &str) -> Result<()> {
+        if self.tenant_context.has_permission(permission) {
+            Ok(())
+        } else {
+            Err(MasterDataError::PermissionDenied {
+                action: action.to_string(),
+            })
+        }
+    }
+
+    async fn decide(&self, transfer_id: Uuid, decision: ApprovalDecision, comment: Option<String>) -> Result<TransferRecord> {
+        self.require_permission(TRANSFER_APPROVAL_PERMISSION, "decide transfer approvals")?;
+        let mut record = self.get_transfer(transfer_id).await?;
+        let mut approval = record.approval.take().ok_or_else(|| MasterDataError::InvalidTransferTransition {
+            transfer_id: transfer_id.to_string(),
+            from: record.transfer.status.as_str().to_string(),
+            to: TransferStatus::Pending.as_str().to_string(),
+        })?;
+
+        let release = transfer_approval::decide(
+            &mut record.transfer,
+            &mut approval,
+            decision,
+            self.tenant_context.user_id,
+            comment,
+            Utc::now(),
+        )?;
+        record.approval = Some(approval);
+        self.repository.apply_decision(&record, release).await?;
+        Ok(record)
+    }
+}
+
+#[async_trait]
+impl StockTransferService for DefaultStockTransferService {
+    async fn create_transfer(&self, request: CreateStockTransferRequest) -> Result<TransferRecord> {
+        self.require_permission("inventory:write", "create stock transfers")?;
+        if request.from_location_id == request.to_location_id {
+            return Err(MasterDataError::ValidationError {
+                field: "to_location_id".to_string(),
+                message: "Cannot transfer to the same location".to_string(),
+            });
+        }
+        if request.quantity <= 0 {
+            return Err(MasterDataError::ValidationError {
+                field: "quantity".to_string(),
+                message: "Transfer quantity must be positive".to_string(),
+            });
+        }
+
+        let tenant_id = self.tenant_context.tenant_id;
+        let facts = self
+            .repository
+            .get_transfer_facts(
+                tenant_id,
+                request.product_id,
+                request.from_location_id,
+                request.to_location_id,
+                request.quantity,
+            )
+            .await?;
+        let rules = self.repository.get_approval_rules(tenant_id).await?;
+        let reasons = transfer_approval::matching_rules(&rules, &facts);
+        if let Some(capacity) = &self.capacity {
+            let incoming = IncomingQuantity {
+                product_id: request.product_id,
+                bin_code: None,
+                quantity: request.quantity as i64,
+            };
+            capacity
+                .check_incoming(request.to_location_id, &[incoming], request.override_capacity)
+                .await?;
+        }
+
+        let now = Utc::now();
+        let user_id = self.tenant_context.user_id;
+        let mut transfer = StockTransfer {
+            id: Uuid::new_v4(),
+            product_id: request.product_id,
+            from_location_id: request.from_location_id,
+            to_location_id: request.to_location_id,
+            quantity: request.quantity,
+            quantity_shipped: None,
+            quantity_received: None,
+            status: TransferStatus::Requested,
+            priority: request.priority,
+            reason: "Manual transfer".to_string(),
+            requested_by: user_id,
+            approved_by: None,
+            shipped_by: None,
+            received_by: None,
+            requested_date: request.requested_date,
+            approved_date: None,
+            shipped_date: None,
+            received_date: None,
+            actual_delivery_date: None,
+            expected_arrival_date: request.expected_arrival_date,
+            tracking_number: None,
+            carrier: None,
+            shipping_cost: None,
+            notes: request.notes,
+            created_at: now,
+            created_by: user_id,
+        };
+        let (approval, reservation) = transfer_approval::open_transfer(&mut transfer, reasons, facts.value(), tenant_id, now);
+
+        let record = TransferRecord { transfer, approval };
+        self.repository.create_transfer(&record, reservation).await?;
+        Ok(record)
+    }
+
+    async fn get_transfer(&self, transfer_id: Uuid) -> Result<TransferRecord> {
+        self.repository
+            .get_transfer(self.tenant_context.tenant_id, transfer_id)
+            .await?
+            .ok_or_else(|| MasterDataError::NotFoundError(format!("Stock transfer {}", transfer_id)))
+    }
+
+    async fn list_transfers(&self, status: Option<TransferStatus>, awaiting_my_approval: bool) -> Result<Vec<TransferRecord>> {
+        let awaiting_approval_by = if awaiting_my_approval {
+            // Nothing awaits a user who cannot approve
+            if !self.tenant_context.has_permission(TRANSFER_APPROVAL_PERMISSION) {
+                return Ok(Vec::new());
+            }
+            Some(self.tenant_context.user_id)
+        } else {
+            None
+        };
+
+        self.repository
+            .list_transfers(
+                self.tenant_context.tenant_id,
+                &TransferListFilter {
+                    status,
+                    awaiting_approval_by,
+                },
+            )
+            .await
+    }
+
+    async fn approve_transfer(&self, transfer_id: Uuid, comment: Option<String>) -> Result<TransferRecord> {
+        self.decide(transfer_id, ApprovalDecision::Approved, comment).await
+    }
+
+    async fn reject_transfer(&self, transfer_id: Uuid, comment: Option<String>) -> Result<TransferRecord> {
+        self.decide(transfer_id, ApprovalDecision::Rejected, comment).await
+    }
+
+    async fn get_approval_rules(&self) -> Result<Vec<TransferApprovalRule>> {
+        self.repository.get_approval_rules(self.tenant_context.tenant_id).await
+    }
+
+    async fn set_approval_rules(&self, rules: Vec<TransferApprovalRule>) -> Result<Vec<TransferApprovalRule>> {
+        self.require_permission(TRANSFER_RULES_PERMISSION, "change transfer approval rules")?;
+        transfer_approval::validate_rules(&rules)?;
+        self.repository
+            .save_approval_rules(self.tenant_context.tenant_id, &rules, self.tenant_context.user_id)
+            .await?;
+        Ok(rules)
+    }
+}
+
+/// Pick waves: grouping reservations, walking order, confirmation and booking
+#[async_trait]
+pub trait PickingService: Send + Sync {
+    /// Group the selected reservations of a location into a wave, lines in path order
+    async fn create_wave(&self, request: CreatePickWaveRequest) -> Result<PickWave>;
+    async fn get_wave(&self, wave_id: Uuid) -> Result<PickWave>;
+    async fn list_waves(&self, status: Option<WaveStatus>) -> Result<Vec<PickWave>>;
+    /// Open waves assigned to the acting user
+    async fn my_pick_lists(&self) -> Result<Vec<PickWave>>;
+    /// Record what was picked for one line; a shortage is re-allocated or backordered
+    async fn confirm_line(&self, wave_id: Uuid, line_id: Uuid, request: ConfirmPickRequest) -> Result<PickLine>;
+    /// Book the confirmed picks as outbound movements and release unconfirmed lines
+    async fn close_wave(&self, wave_id: Uuid) -> Result<PickWave>;
+    async fn get_summary(&self, wave_id: Uuid) -> Result<WaveSummary>;
+}
+
+pub struct DefaultPickingService {
+    repository: Arc<dyn PickWaveRepository>,
+    tenant_context: TenantContext,
+    path_strategy: Arc<dyn PickPathStrategy>,
+}
+
+impl DefaultPickingService {
+    pub fn new(repository: Arc<dyn PickWaveRepository>, tenant_context: TenantContext) -> Self {
+        Self {
+            repository,
+            tenant_context,
+            path_strategy: Arc::new(SerpentinePath),
+        }
+    }
+
+    /// Order new waves with another strategy
+    pub fn with_path_strategy(mut self, path_strategy: Arc<dyn PickPathStrategy>) -> Self {
+        self.path_strategy = path_strategy;
+        self
+    }
+}
+
+#[async_trait]
+impl PickingService for DefaultPickingService {
+    async fn create_wave(&self, request: CreatePickWaveRequest) -> Result<PickWave> {
+        let tenant_id = self.tenant_context.tenant_id;
+        let candidates = self
+            .repository
+            .find_pick_candidates(tenant_id, request.location_id, &request.selection)
+            .await?;
+        let wave = PickWave::new(
+            tenant_id,
+            &request,
+            candidates,
+            self.path_strategy.as_ref(),
+            self.tenant_context.user_id,
+            Utc::now(),
+        )?;
+        self.repository.create_wave(&wave).await?;
+        Ok(wave)
+    }
+
+    async fn get_wave(&self, wave_id: Uuid) -> Result<PickWave> {
+        self.repository
+            .get_wave(self.tenant_context.tenant_id, wave_id)
+            .await?
+            .ok_or_else(|| MasterDataError::NotFoundError(format!("Pick wave {}", wave_id)))
+    }
+
+    async fn list_waves(&self, status: Option<WaveStatus>) -> Result<Vec<PickWave>> {
+        self.repository.list_waves(self.tenant_context.tenant_id, status, None).await
+    }
+
+    async fn my_pick_lists(&self) -> Result<Vec<PickWave>> {
+        self.repository
+            .list_waves(
+                self.tenant_context.tenant_id,
+                Some(WaveStatus::Open),
+                Some(self.tenant_context.user_id),
+            )
+            .await
+    }
+
+    async fn confirm_line(&self, wave_id: Uuid, line_id: Uuid, request: ConfirmPickRequest) -> Result<PickLine> {
+        let mut wave = self.get_wave(wave_id).await?;
+        let shortage = wave.confirm_line(line_id, &request, self.tenant_context.user_id, Utc::now())?;
+
+        let reallocation = if shortage > 0 {
+            let product_id = wave
+                .lines
+                .iter()
+                .find(|line| line.id == line_id)
+                .map(|line| line.product_id)
+                .ok_or_else(|| MasterDataError::NotFoundError(format!("Pick line {}", line_id)))?;
+            let options = self
+                .repository
+                .find_stock_options(self.tenant_context.tenant_id, product_id, wave.location_id)
+                .await?;
+            wave.resolve_short_pick(line_id, &options)?
+        } else {
+            None
+        };
+
+        self.repository.confirm_line(&wave, line_id, reallocation).await?;
+        wave.lines
+            .into_iter()
+            .find(|line| line.id == line_id)
+            .ok_or_else(|| MasterDataError::NotFoundError(format!("Pick line {}", line_id)))
+    }
+
+    async fn close_wave(&self, wave_id: Uuid) -> Result<PickWave> {
+        let mut wave = self.get_wave(wave_id).await?;
+        let closing = wave.close(self.tenant_context.user_id, Utc::now())?;
+        self.repository.close_wave(&wave, &closing).await?;
+        Ok(wave)
+    }
+
+    async fn get_summary(&self, wave_id: Uuid) -> Result<WaveSummary> {
+        Ok(self.get_wave(wave_id).await?.summary())
+    }
+}
+
+/// Storage capacity of locations and bins, and the checks against it
+#[async_trait]
+pub trait LocationCapacityService: Send + Sync {
+    /// Occupancy of every capacity of the location, including stock on its way there
+    async fn get_utilization(&self, location_id: Uuid) -> Result<CapacityAssessment>;
+    async fn get_capacities(&self, location_id: Uuid) -> Result<Vec<LocationCapacity>>;
+    /// Replace the capacities of the location and its bins
+    async fn set_capacities(&self, location_id: Uuid, capacities: Vec<LocationCapacityInput>) -> Result<Vec<LocationCapacity>>;
+    /// Replace the explicit space per unit of a product
+    async fn set_space_overrides(&self, product_id: Uuid, overrides: HashMap<CapacityUnit, f64>) -> Result<()>;
+    /// Check stock about to arrive at a location. Raises the warnings of the
+    /// check and fails if a capacity would be exceeded without a permitted override.
+    async fn check_incoming(
+        &self,
+        location_id: Uuid,
+        incoming: &[IncomingQuantity],
+        override_capacity: bool,
+    ) -> Result<CapacityAssessment>;
+}
+
+pub struct DefaultLocationCapacityService {
+    repository: Arc<dyn LocationCapacityRepository>,
+    tenant_context: TenantContext,
+    settings: CapacitySettings,
+}
+
+impl DefaultLocationCapacityService {
+    pub fn new(
+        repository: Arc<dyn LocationCapacityRepository>,
+        tenant_context: TenantContext,
+        settings: CapacitySettings,
+    ) -> Self {
+        Self {
+            repository,
+            tenant_context,
+            settings,
+        }
+    }
+
+    fn require_permission(&self, permission: &str, action: &str) -> Result<()> {
+        if self.tenant_context.has_permission(permission) {
+            Ok(())
+        } else {
+            Err(MasterDataError::PermissionDenied {
+                action: action.to_string(),
+            })
+        }
+    }
+
+    async fn assess(&self, location_id: Uuid, incoming: &[IncomingQuantity]) -> Result<CapacityAssessment> {
+        let tenant_id = self.tenant_context.tenant_id;
+        let capacities = self.repository.get_capacities(tenant_id, location_id).await?;
+        if capacities.is_empty() {
+            return Ok(capacity::assess(location_id, &[], &[], incoming, &HashMap::new(), &self.settings));
+        }
+        let stored = self.repository.get_stored_quantities(tenant_id, location_id).await?;
+        let mut product_ids: Vec<Uuid> = stored
+            .iter()
+            .map(|item| item.product_id)
+            .chain(incoming.iter().map(|item| item.product_id))
+            .collect();
+        product_ids.sort();
+        product_ids.dedup();
+        let products = self.repository.get_product_space(tenant_id, &product_ids).await?;
+        Ok(capacity::assess(location_id, &capacities, &stored, incoming, &products, &self.settings))
+    }
+}
+
+#[async_trait]
+impl LocationCapacityService for DefaultLocationCapacityService {
+    async fn get_utilization(&self, location_id: Uuid) -> Result<CapacityAssessment> {
+        self.assess(location_id, &[]).await
+    }
+
+    async fn get_capacities(&self, location_id: Uuid) -> Result<Vec<LocationCapacity>> {
+        self.repository
+            .get_capacities(self.tenant_context.tenant_id, location_id)
+            .await
+    }
+
+    async fn set_capacities(&self, location_id: Uuid, capacities: Vec<LocationCapacityInput>) -> Result<Vec<LocationCapacity>> {
+        self.require_permission("inventory:write", "change location capacities")?;
+        let capacities = capacity::capacities_for(location_id, capacities)?;
+        self.repository
+            .save_capacities(self.tenant_context.tenant_id, location_id, &capacities, self.tenant_context.user_id)
+            .await?;
+        Ok(capacities)
+    }
+
+    async fn set_space_overrides(&self, product_id: Uuid, overrides: HashMap<CapacityUnit, f64>) -> Result<()> {
+        self.require_permission("inventory:write", "change product space factors")?;
+        capacity::validate_overrides(&overrides)?;
+        self.repository
+            .save_space_overrides(self.tenant_context.tenant_id, product_id, &overrides, self.tenant_context.user_id)
+            .await
+    }
+
+    async fn check_incoming(
+        &self,
+        location_id: Uuid,
+        incoming: &[IncomingQuantity],
+        override_capacity: bool,
+    ) -> Result<CapacityAssessment> {
+        let assessment = self.assess(location_id, incoming).await?;
+        let may_override = self.tenant_context.has_permission(CAPACITY_OVERRIDE_PERMISSION);
+        for warning in capacity::enforce(&assessment, override_capacity, may_override)? {
+            erp_core::warnings::warn(warning);
+        }
+        Ok(assessment)
+    }
+}
+
+/// Receipt of purchase order deliveries into stock
+#[async_trait]
+pub trait PurchaseReceiptService: Send + Sync {
+    /// Book delivered units into the order's location after checking its capacity
+    async fn receive_purchase_order(
+        &self,
+        purchase_order_id: Uuid,
+        request: ReceivePurchaseOrderRequest,
+    ) -> Result<PurchaseOrderReceipt>;
+
+    /// Every receipt event of the order, per line
+    async fn receipt_history(&self, purchase_order_id: Uuid) -> Result<ReceiptHistory>;
+}
+
+pub struct DefaultPurchaseReceiptService {
+    repository: Arc<dyn PurchaseReceiptRepository>,
+    capacity: Arc<dyn LocationCapacityService>,
+    tenant_context: TenantContext,
+    settings: ReceivingSettings,
+    inspections: Option<Arc<dyn QualityInspectionService>>,
+}
+
+impl DefaultPurchaseReceiptService {
+    pub fn new(
+        repository: Arc<dyn PurchaseReceiptRepository>,
+        capacity: Arc<dyn LocationCapacityService>,
+        tenant_context: TenantContext,
+        settings: ReceivingSettings,
+    ) -> Self {
+        Self {
+            repository,
+            capacity,
+            tenant_context,
+            settings,
+            inspections: None,
+        }
+    }
+
+    /// Schedule the incoming-goods inspections of received batches right away
+    pub fn with_inspections(mut self, inspections: Arc<dyn QualityInspectionService>) -> Self {
+        self.inspections = Some(inspections);
+        self
+    }
+
+    async fn order(&self, purchase_order_id: Uuid) -> Result<ReceivableOrder> {
+        self.repository
+            .get_receivable_order(self.tenant_context.tenant_id, purchase_order_id)
+            .await?
+            .ok_or_else(|| MasterDataError::NotFoundError(format!("Purchase order {}", purchase_order_id)))
+    }
+}
+
+#[async_trait]
+impl PurchaseReceiptService for DefaultPurchaseReceiptService {
+    async fn receive_purchase_order(
+        &self,
+        purchase_order_id: Uuid,
+        request: ReceivePurchaseOrderRequest,
+    ) -> Result<PurchaseOrderReceipt> {
+        if !self.tenant_context.has_permission("inventory:write") {
+            return Err(MasterDataError::PermissionDenied {
+                action: "receive purchase orders".to_string(),
+            });
+        }
+        let order = self.order(purchase_order_id).await?;
+
+        let lines = receiving::plan_receipt(&order, &request, &self.settings)?;
+        self.capacity
+            .check_incoming(order.location_id, &receiving::incoming(&lines), request.override_capacity)
+            .await?;
+
+        let received_by = self.tenant_context.user_id;
+        let received_at = Utc::now();
+        let booking = receiving::book(&order, lines, self.tenant_context.tenant_id, received_by, received_at);
+        self.repository
+            .record_receipt(self.tenant_context.tenant_id, &order, &booking)
+            .await?;
+
+        // The stock is booked either way; the daily inspection run catches up
+        // on batches whose inspection could not be scheduled here
+        let inspections = match &self.inspections {
+            Some(inspections) if !booking.movements.is_empty() => {
+                inspections.goods_received(purchase_order_id).await.unwrap_or_else(|error| {
+                    tracing::warn!(%purchase_order_id, %error, "scheduling incoming inspections failed");
+                    Vec::new()
+                })
+            }
+            _ => Vec::new(),
+        };
+        Ok(PurchaseOrderReceipt {
+            receipt_id: booking.receipt_id,
+            purchase_order_id,
+            location_id: order.location_id,
+            status: booking.status.to_string(),
+            lines: booking.lines,
+            events: booking.events,
+            received_by,
+            received_at,
+            inspections,
+        })
+    }
+
+    async fn receipt_history(&self, purchase_order_id: Uuid) -> Result<ReceiptHistory> {
+        if !self.tenant_context.has_permission("inventory:read") {
+            return Err(MasterDataError::PermissionDenied {
+                action: "view purchase order receipts".to_string(),
+            });
+        }
+        let order = self.order(purchase_order_id).await?;
+        let events = self
+            .repository
+            .get_receipt_events(self.tenant_context.tenant_id, purchase_order_id)
+            .await?;
+        Ok(receiving::history(&order, events))
+    }
+}
+
+/// Stock valuation, KPI, margin and dashboard reports in the tenant's base currency
+#[async_trait]
+pub trait InventoryValuationService: Send + Sync {
+    /// Cost value of the stock, converted with the rates effective on `valuation_date`
+    async fn valuation(&self, filter: &StockValueFilter, valuation_date: NaiveDate) -> Result<StockValuation>;
+    async fn kpis(&self, filter: &StockValueFilter, valuation_date: NaiveDate) -> Result<StockKpis>;
+    async fn product_margins(&self, filter: &StockValueFilter, valuation_date: NaiveDate) -> Result<MarginReport>;
+    async fn dashboard(&self, filter: &StockValueFilter, valuation_date: NaiveDate) -> Result<ValuationDashboard>;
+}
+
+pub struct DefaultInventoryValuationService {
+    repository: Arc<dyn InventoryValuationRepository>,
+    rates: Arc<dyn ExchangeRateRepository>,
+    provider: Arc<dyn ExchangeRateProvider>,
+    tenant_context: TenantContext,
+}
+
+impl DefaultInventoryValuationService {
+    pub fn new(
+        repository: Arc<dyn InventoryValuationRepository>,
+        rates: Arc<dyn ExchangeRateRepository>,
+        provider: Arc<dyn ExchangeRateProvider>,
+        tenant_context: TenantContext,
+    ) -> Self {
+        Self {
+            repository,
+            rates,
+            provider,
+            tenant_context,
+        }
+    }
+
+    /// Our own stock and the rates converting each of its currencies into the base currency
+    async fn load(&self, filter: &StockValueFilter, valuation_date: NaiveDate) -> Result<(Vec<StockValueLine>, CurrencyConversion)> {
+        let tenant_id = self.tenant_context.tenant_id;
+        let lines = valuation::own_stock(self.repository.get_stock_value_lines(tenant_id, filter).await?);
+        let base_currency = self.rates.base_currency(tenant_id).await?;
+        let conversion = resolve_conversion(
+            self.provider.as_ref(),
+            tenant_id,
+            &base_currency,
+            &valuation::currencies(&lines),
+            valuation_date,
+        )
+        .await?;
+        Ok((lines, conversion))
+    }
+}
+
+#[async_trait]
+impl InventoryValuationService for DefaultInventoryValuationService {
+    async fn valuation(&self, filter: &StockValueFilter, valuation_date: NaiveDate) -> Result<StockValuation> {
+        let (lines, conversion) = self.load(filter, valuation_date).await?;
+        valuation::value_stock(&lines, &conversion)
+    }
+
+    async fn kpis(&self, filter: &StockValueFilter, valuation_date: NaiveDate) -> Result<StockKpis> {
+        let (lines, conversion) = self.load(filter, valuation_date).await?;
+        valuation::stock_kpis(&lines, &conversion)
+    }
+
+    async fn product_margins(&self, filter: &StockValueFilter, valuation_date: NaiveDate) -> Result<MarginReport> {
+        let (lines, conversion) = self.load(filter, valuation_date).await?;
+        valuation::product_margins(&lines, &conversion)
+    }
+
+    async fn dashboard(&self, filter: &StockValueFilter, valuation_date: NaiveDate) -> Result<ValuationDashboard> {
+        let (lines, conversion) = self.load(filter, valuation_date).await?;
+        valuation::valuation_dashboard(&lines, &conversion)
+    }
+}
+
+/// Stock suppliers keep in our locations until it is consumed
+#[async_trait]
+pub trait ConsignmentService: Send + Sync {
+    /// Book units in as the supplier's after checking the location's capacity
+    async fn receive(&self, request: ConsignmentReceiptRequest) -> Result<ConsignmentMovement>;
+    /// End the supplier's ownership of units, priced with their catalog entry
+    async fn consume(&self, request: ConsignmentConsumptionRequest) -> Result<ConsignmentMovement>;
+    /// Move units of the supplier to another location; they stay the supplier's
+    async fn transfer(&self, request: ConsignmentTransferRequest) -> Result<Vec<ConsignmentMovement>>;
+    async fn balances(&self, supplier_id: Option<Uuid>) -> Result<Vec<ConsignmentBalance>>;
+    /// The supplier's stock on hand and what was consumed from `from` to `to`
+    async fn settlement(&self, supplier_id: Uuid, from: NaiveDate, to: NaiveDate) -> Result<ConsignmentSettlement>;
+}
+
+pub struct DefaultConsignmentService {
+    repository: Arc<dyn ConsignmentRepository>,
+    capacity: Arc<dyn LocationCapacityService>,
+    tenant_context: TenantContext,
+}
+
+impl DefaultConsignmentService {
+    pub fn new(
+        repository: Arc<dyn ConsignmentRepository>,
+        capacity: Arc<dyn LocationCapacityService>,
+        tenant_context: TenantContext,
+    ) -> Self {
+        Self {
+            repository,
+            capacity,
+            tenant_context,
+        }
+    }
+
+    fn require_write(&self, action: &str) -> Result<()> {
+        if !self.tenant_context.has_permission("inventory:write") {
+            return Err(MasterDataError::PermissionDenied {
+                action: action.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    async fn check_capacity(&self, movement: &ConsignmentMovement, override_capacity: bool) -> Result<()> {
+        let incoming = IncomingQuantity {
+            product_id: movement.product_id,
+            bin_code: None,
+            quantity: movement.quantity as i64,
+        };
+        self.capacity
+            .check_incoming(movement.location_id, &[incoming], override_capacity)
+            .await?;
+        Ok(())
+    }
+
+    async fn record(&self, movements: &[ConsignmentMovement]) -> Result<()> {
+        let stock: Vec<InventoryMovement> = movements.iter().filter_map(consignment::stock_movement).collect();
+        self.repository
+            .record(self.tenant_context.tenant_id, movements, &stock)
+            .await
+    }
+}
+
+#[async_trait]
+impl ConsignmentService for DefaultConsignmentService {
+    async fn receive(&self, request: ConsignmentReceiptRequest) -> Result<ConsignmentMovement> {
+        self.require_write("receive consignment stock")?;
+        let movement = consignment::receipt(&request, self.tenant_context.user_id, Utc::now())?;
+        self.repository
+            .check_references(self.tenant_context.tenant_id, request.supplier_id, request.product_id, &[request.location_id])
+            .await?;
+        self.check_capacity(&movement, request.override_capacity).await?;
+        self.record(std::slice::from_ref(&movement)).await?;
+        Ok(movement)
+    }
+
+    async fn consume(&self, request: ConsignmentConsumptionRequest) -> Result<ConsignmentMovement> {
+        self.require_write("consume consignment stock")?;
+        let tenant_id = self.tenant_context.tenant_id;
+        self.repository
+            .check_references(tenant_id, request.supplier_id, request.product_id, &[request.location_id])
+            .await?;
+        let balance = self
+            .repository
+            .get_balance(tenant_id, request.supplier_id, request.product_id, request.location_id)
+            .await?;
+        let now = Utc::now();
+        let entries = self
+            .repository
+            .get_catalog_entries(tenant_id, request.supplier_id, request.product_id)
+            .await?;
+        let price = consignment::consignment_price(&entries, request.quantity, now).ok_or_else(|| {
+            MasterDataError::ValidationError {
+                field: "product_id".to_string(),
+                message: "The supplier has no valid catalog price for the product".to_string(),
+            }
+        })?;
+
+        let movement = consignment::consumption(&request, balance, &price, self.tenant_context.user_id, now)?;
+        self.record(std::slice::from_ref(&movement)).await?;
+        Ok(movement)
+    }
+
+    async fn transfer(&self, request: ConsignmentTransferRequest) -> Result<Vec<ConsignmentMovement>> {
+        self.require_write("transfer consignment stock")?;
+        let tenant_id = self.tenant_context.tenant_id;
+        self.repository
+            .check_references(
+                tenant_id,
+                request.supplier_id,
+                request.product_id,
+                &[request.from_location_id, request.to_location_id],
+            )
+            .await?;
+        let balance = self
+            .repository
+            .get_balance(tenant_id, request.supplier_id, request.product_id, request.from_location_id)
+            .await?;
+
+        let movements = consignment::transfer(&request, balance, self.tenant_context.user_id, Utc::now())?;
+        self.check_capacity(&movements[1], request.override_capacity).await?;
+        self.record(&movements).await?;
+        Ok(movements.to_vec())
+    }
+
+    async fn balances(&self, supplier_id: Option<Uuid>) -> Result<Vec<ConsignmentBalance>> {
+        self.repository.list_balances(self.tenant_context.tenant_id, supplier_id).await
+    }
+
+    async fn settlement(&self, supplier_id: Uuid, from: NaiveDate, to: NaiveDate) -> Result<ConsignmentSettlement> {
+        if from > to {
+            return Err(MasterDataError::ValidationError {
+                field: "from".to_string(),
+                message: "The period must not end before it starts".to_string(),
+            });
+        }
+        let tenant_id = self.tenant_context.tenant_id;
+        let balances = self.repository.list_balances(tenant_id, Some(supplier_id)).await?;
+        let movements = self.repository.list_movements(tenant_id, supplier_id, from, to).await?;
+        Ok(consignment::settlement(supplier_id, from, to, &balances, &movements))
+    }
+}
+
+/// Available-to-promise of products; see [`crate::inventory::atp`]
+#[async_trait]
+pub trait AvailableToPromiseService: Send + Sync {
+    /// Timeline of the product at the location, or across all sellable locations
+    async fn timeline(&self, product_id: Uuid, location_id: Option<Uuid>) -> Result<AtpTimeline>;
+    /// Check order lines in turn, each after the ones before it were promised
+    async fn check_lines(&self, lines: &[AtpBatchLine]) -> Result<Vec<AtpLineResult>>;
+}
+
+pub struct DefaultAvailableToPromiseService {
+    repository: Arc<dyn AvailableToPromiseRepository>,
+    tenant_context: TenantContext,
+}
+
+impl DefaultAvailableToPromiseService {
+    pub fn new(repository: Arc<dyn AvailableToPromiseRepository>, tenant_context: TenantContext) -> Self {
+        Self { repository, tenant_context }
+    }
+
+    async fn build_timeline(&self, product_id: Uuid, location_id: Option<Uuid>) -> Result<AtpTimeline> {
+        let tenant_id = self.tenant_context.tenant_id;
+        let stock = self.repository.get_stock(tenant_id, product_id, location_id).await?;
+        let receipts = self
+            .repository
+            .get_scheduled_receipts(tenant_id, product_id, location_id)
+            .await?;
+        Ok(AtpTimeline::build(product_id, location_id, Utc::now().date_naive(), &stock, &receipts))
+    }
+}
+
+#[async_trait]
+impl AvailableToPromiseService for DefaultAvailableToPromiseService {
+    async fn timeline(&self, product_id: Uuid, location_id: Option<Uuid>) -> Result<AtpTimeline> {
+        self.repository
+            .check_references(self.tenant_context.tenant_id, &[product_id], &location_id.into_iter().collect::<Vec<_>>())
+            .await?;
+        self.build_timeline(product_id, location_id).await
+    }
+
+    async fn check_lines(&self, lines: &[AtpBatchLine]) -> Result<Vec<AtpLineResult>> {
+        if lines.is_empty() || lines.len() > MAX_BATCH_LINES {
+            return Err(MasterDataError::ValidationError {
+                field: "lines".to_string(),
+                message: format!("Between 1 and {} lines can be checked at once", MAX_BATCH_LINES),
+            });
+        }
+        if lines.iter().any(|line| line.quantity <= 0) {
+            return Err(MasterDataError::ValidationError {
+                field: "quantity".to_string(),
+                message: "Quantity must be positive".to_string(),
+            });
+        }
+        let product_ids: Vec<Uuid> = lines.iter().map(|line| line.product_id).collect();
+        let location_ids: Vec<Uuid> = lines.iter().filter_map(|line| line.location_id).collect();
+        self.repository
+            .check_references(self.tenant_context.tenant_id, &product_ids, &location_ids)
+            .await?;
+
+        // Lines of the same product and location draw on one timeline
+        let mut timelines: HashMap<(Uuid, Option<Uuid>), AtpTimeline> = HashMap::new();
+        let mut results = Vec::with_capacity(lines.len());
+        for line in lines {
+            let timeline = match timelines.entry((line.product_id, line.location_id)) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => entry.insert(self.build_timeline(line.product_id, line.location_id).await?),
+            };
+            results.push(atp::promise_line(timeline, line));
+        }
+        Ok(results)
+    }
+}
+
+#[async_trait]
+pub trait InventorySnapshotService: Send + Sync {
+    /// Snapshot the location today, of the products in `scope` or of all of them
+    async fn create_snapshot(&self, location_id: Uuid, scope: Option<SnapshotScope>) -> Result<SnapshotRun>;
+    async fn get_snapshot(&self, snapshot_id: Uuid) -> Result<(SnapshotRun, Vec<SnapshotLine>)>;
+    async fn list_snapshots(&self, location_id: Option<Uuid>, limit: i64) -> Result<Vec<SnapshotRun>>;
+    /// What changed from one snapshot to a later one; fails with a
+    /// `ValidationError` unless both are of the same location and scope
+    async fn compare_snapshots(&self, base_id: Uuid, other_id: Uuid) -> Result<SnapshotComparison>;
+    async fn list_schedules(&self) -> Result<Vec<SnapshotSchedule>>;
+    /// Take a month-end snapshot of the location with the scope
+    async fn set_schedule(&self, location_id: Uuid, scope: Option<SnapshotScope>, enabled: bool) -> Result<SnapshotSchedule>;
+    async fn delete_schedule(&self, location_id: Uuid) -> Result<bool>;
+}
+
+pub struct DefaultInventorySnapshotService {
+    repository: Arc<dyn InventorySnapshotRepository>,
+    tenant_context: TenantContext,
+}
+
+impl DefaultInventorySnapshotService {
+    pub fn new(repository: Arc<dyn InventorySnapshotRepository>, tenant_context: TenantContext) -> Self {
+        Self { repository, tenant_context }
+    }
+
+    fn require_write(&self, action: &str) -> Result<()> {
+        if !self.tenant_context.has_permission("inventory:write") {
+            return Err(MasterDataError::PermissionDenied {
+                action: action.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    async fn load(&self, snapshot_id: Uuid) -> Result<SnapshotRun> {
+        self.repository
+            .get_snapshot(self.tenant_context.tenant_id, snapshot_id)
+            .await?
+            .ok_or_else(|| MasterDataError::NotFoundError(format!("Inventory snapshot {} not found", snapshot_id)))
+    }
+}
+
+fn checked_scope(scope: Option<SnapshotScope>) -> Result<SnapshotScope> {
+    let scope = scope.unwrap_or_default().normalized();
+    scope.validate()?;
+    Ok(scope)
+}
+
+#[async_trait]
+impl InventorySnapshotService for DefaultInventorySnapshotService {
+    async fn create_snapshot(&self, location_id: Uuid, scope: Option<SnapshotScope>) -> Result<SnapshotRun> {
+        self.require_write("create inventory snapshots")?;
+        let scope = checked_scope(scope)?;
+        self.repository
+            .create_snapshot(
+                self.tenant_context.tenant_id,
+                location_id,
+                Utc::now().date_naive(),
+                &scope,
+                SnapshotTrigger::Manual,
+                Some(self.tenant_context.user_id),
+            )
+            .await
+    }
+
+    async fn get_snapshot(&self, snapshot_id: Uuid) -> Result<(SnapshotRun, Vec<SnapshotLine>)> {
+        let run = self.load(snapshot_id).await?;
+        let lines = self.repository.get_snapshot_lines(self.tenant_context.tenant_id, snapshot_id).await?;
+        Ok((run, lines))
+    }
+
+    async fn list_snapshots(&self, location_id: Option<Uuid>, limit: i64) -> Result<Vec<SnapshotRun>> {
+        self.repository
+            .list_snapshots(self.tenant_context.tenant_id, location_id, limit.clamp(1, 500))
+            .await
+    }
+
+    async fn compare_snapshots(&self, base_id: Uuid, other_id: Uuid) -> Result<SnapshotComparison> {
+        let base = self.load(base_id).await?;
+        let other = self.load(other_id).await?;
+        // Refused before any line is read
+        snapshot::ensure_comparable(&base, &other)?;
+        let tenant_id = self.tenant_context.tenant_id;
+        let base_lines = self.repository.get_snapshot_lines(tenant_id, base_id).await?;
+        let other_lines = self.repository.get_snapshot_lines(tenant_id, other_id).await?;
+        snapshot::compare(&base, &base_lines, &other, &other_lines)
+    }
+
+    async fn list_schedules(&self) -> Result<Vec<SnapshotSchedule>> {
+        self.repository.list_schedules(Some(self.tenant_context.tenant_id)).await
+    }
+
+    async fn set_schedule(&self, location_id: Uuid, scope: Option<SnapshotScope>, enabled: bool) -> Result<SnapshotSchedule> {
+        self.require_write("schedule inventory snapshots")?;
+        let schedule = SnapshotSchedule {
+            tenant_id: self.tenant_context.tenant_id,
+            location_id,
+            scope: checked_scope(scope)?,
+            enabled,
+            last_run_on: None,
+            updated_by: Some(self.tenant_context.user_id),
+            updated_at: Utc::now(),
+        };
+        self.repository.save_schedule(&schedule).await
+    }
+
+    async fn delete_schedule(&self, location_id: Uuid) -> Result<bool> {
+        self.require_write("schedule inventory snapshots")?;
+        self.repository.delete_schedule(self.tenant_context.tenant_id, location_id).await
+    }
+}
+
+#[async_trait]
+pub trait InventoryCostingService: Send + Sync {
+    async fn settings(&self) -> Result<CostingSettings>;
+    async fn update_settings(&self, settings: CostingSettings) -> Result<CostingSettings>;
+    /// Inbound movements from `from` to `to`, inclusive, by where their cost came from
+    async fn cost_source_report(&self, from: NaiveDate, to: NaiveDate) -> Result<CostSourceReport>;
+    /// Reverse the inbound movement at its cost and book it again at the new one
+    async fn restate_cost(&self, movement_id: Uuid, request: CostRestatementRequest) -> Result<CostRestatement>;
+}
+
+pub struct DefaultInventoryCostingService {
+    repository: Arc<dyn InventoryCostingRepository>,
+    tenant_context: TenantContext,
+}
+
+impl DefaultInventoryCostingService {
+    pub fn new(repository: Arc<dyn InventoryCostingRepository>, tenant_context: TenantContext) -> Self {
+        Self { repository, tenant_context }
+    }
+
+    fn require_write(&self, action: &str) -> Result<()> {
+        if !self.tenant_context.has_permission("inventory:write") {
+            return Err(MasterDataError::PermissionDenied {
+                action: action.to_string(),
+            });
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl InventoryCostingService for DefaultInventoryCostingService {
+    async fn settings(&self) -> Result<CostingSettings> {
+        self.repository.get_settings(self.tenant_context.tenant_id).await
+    }
+
+    async fn update_settings(&self, settings: CostingSettings) -> Result<CostingSettings> {
+        self.require_write("change inventory costing settings")?;
+        settings.validate()?;
+        self.repository
+            .save_settings(self.tenant_context.tenant_id, &settings, self.tenant_context.user_id)
+            .await
+    }
+
+    async fn cost_source_report(&self, from: NaiveDate, to: NaiveDate) -> Result<CostSourceReport> {
+        if to < from {
+            return Err(MasterDataError::ValidationError {
+                field: "to".to_string(),
+                message: "The period ends before it starts".to_string(),
+            });
+        }
+        let movements = self.repository.list_movements(self.tenant_context.tenant_id, from, to).await?;
+        Ok(costing::cost_source_report(&movements, from, to))
+    }
+
+    async fn restate_cost(&self, movement_id: Uuid, request: CostRestatementRequest) -> Result<CostRestatement> {
+        self.require_write("restate inventory costs")?;
+        let (movement, reversed) = self
+            .repository
+            .get_movement(self.tenant_context.tenant_id, movement_id)
+            .await?
+            .ok_or_else(|| MasterDataError::NotFoundError(format!("Inventory movement {} not found", movement_id)))?;
+        let restatement = costing::restatement(&movement, reversed, &request, Utc::now())?;
+        self.repository.record_restatement(&restatement, self.tenant_context.user_id).await?;
+        Ok(restatement)
+    }
+}
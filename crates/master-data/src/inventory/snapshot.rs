@@ -0,0 +1,421 @@
+//! # Scoped Inventory Snapshots
+//!
+//! A snapshot records the stock of one location on a day. Without a scope it
+//! covers every product stocked there; with a [`SnapshotScope`] only the
+//! products matching all of its conditions, so a month-end snapshot of the
+//! class A products does not have to read the whole warehouse. Each
+//! [`SnapshotRun`] keeps the scope it was taken with and says whether it is
+//! partial, and [`compare`] refuses two snapshots whose scopes differ: a
+//! product missing from one of them would otherwise read as stock that came
+//! or went.
+//!
+//! A [`SnapshotSchedule`] asks for a snapshot of a location on the last day
+//! of every month. A month end the scheduler missed is not made up later, as
+//! the stock would no longer be that of the month end.
+//!
+//! The functions here are pure; snapshots are taken and read by
+//! [`InventorySnapshotRepository`](super::repository::InventorySnapshotRepository)
+//! and [`InventorySnapshotService`](super::service::InventorySnapshotService).
+
+use crate::error::{MasterDataError, Result};
+use crate::inventory::model::ABCClassification;
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+/// Most product ids a scope may list
+pub const MAX_SCOPE_PRODUCTS: usize = 10_000;
+
+/// Which products of a location a snapshot covers; a product must match every
+/// condition given, and a scope without conditions covers the whole location
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SnapshotScope {
+    pub product_ids: Vec<Uuid>,
+    pub category_id: Option<Uuid>,
+    pub abc_class: Option<ABCClassification>,
+    /// Lowest unit cost in cents
+    pub min_unit_value: Option<i64>,
+}
+
+impl SnapshotScope {
+    /// The same scope with its product ids sorted and without duplicates, so
+    /// equal scopes compare equal
+    pub fn normalized(mut self) -> Self {
+        self.product_ids.sort();
+        self.product_ids.dedup();
+        self
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.product_ids.is_empty() && self.category_id.is_none() && self.abc_class.is_none() && self.min_unit_value.is_none()
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.product_ids.len() > MAX_SCOPE_PRODUCTS {
+            return Err(MasterDataError::ValidationError {
+                field: "scope.product_ids".to_string(),
+                message: format!("A snapshot scope lists at most {} products", MAX_SCOPE_PRODUCTS),
+            });
+        }
+        if self.abc_class == Some(ABCClassification::X) {
+            return Err(MasterDataError::ValidationError {
+                field: "scope.abc_class".to_string(),
+                message: "Stock is classified A, B or C per location".to_string(),
+            });
+        }
+        if self.min_unit_value.is_some_and(|value| value < 0) {
+            return Err(MasterDataError::ValidationError {
+                field: "scope.min_unit_value".to_string(),
+                message: "Minimum unit value cannot be negative".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Whether the line belongs in a snapshot of this scope; the repository
+    /// applies the same conditions in its query
+    pub fn matches(&self, line: &SnapshotLine) -> bool {
+        (self.product_ids.is_empty() || self.product_ids.contains(&line.product_id))
+            && self.category_id.is_none_or(|category_id| line.category_id == Some(category_id))
+            && self.abc_class.as_ref().is_none_or(|class| line.abc_class.as_ref() == Some(class))
+            && self
+                .min_unit_value
+                .is_none_or(|min| line.unit_cost.is_some_and(|unit_cost| unit_cost >= min))
+    }
+
+    /// The scope in words, for error messages and reports
+    pub fn describe(&self) -> String {
+        if self.is_full() {
+            return "the whole location".to_string();
+        }
+        let mut parts = Vec::new();
+        if !self.product_ids.is_empty() {
+            parts.push(format!("{} listed products", self.product_ids.len()));
+        }
+        if let Some(category_id) = self.category_id {
+            parts.push(format!("category {}", category_id));
+        }
+        if let Some(class) = &self.abc_class {
+            parts.push(format!("ABC class {}", abc_label(class)));
+        }
+        if let Some(min) = self.min_unit_value {
+            parts.push(format!("unit value of at least {}.{:02}", min / 100, min % 100));
+        }
+        parts.join(", ")
+    }
+}
+
+/// Label of an ABC class as stored in `location_items.abc_classification`
+pub fn abc_label(class: &ABCClassification) -> &'static str {
+    match class {
+        ABCClassification::A => "A",
+        ABCClassification::B => "B",
+        ABCClassification::C => "C",
+        ABCClassification::X => "X",
+    }
+}
+
+pub fn parse_abc_label(label: &str) -> Option<ABCClassification> {
+    match label {
+        "A" => Some(ABCClassification::A),
+        "B" => Some(ABCClassification::B),
+        "C" => Some(ABCClassification::C),
+        "X" => Some(ABCClassification::X),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SnapshotTrigger {
+    Manual,
+    MonthEnd,
+}
+
+impl SnapshotTrigger {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SnapshotTrigger::Manual => "manual",
+            SnapshotTrigger::MonthEnd => "month_end",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "manual" => Some(SnapshotTrigger::Manual),
+            "month_end" => Some(SnapshotTrigger::MonthEnd),
+            _ => None,
+        }
+    }
+}
+
+/// One snapshot of a location
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotRun {
+    pub id: Uuid,
+    pub tenant_id: Uuid,
+    pub location_id: Uuid,
+    pub snapshot_date: NaiveDate,
+    pub scope: SnapshotScope,
+    /// Covers only part of the location's products
+    pub partial: bool,
+    pub trigger: SnapshotTrigger,
+    pub line_count: i64,
+    /// Cents
+    pub total_value: i64,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Stock of one product in a snapshot
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotLine {
+    pub product_id: Uuid,
+    pub category_id: Option<Uuid>,
+    pub abc_class: Option<ABCClassification>,
+    pub quantity_on_hand: i64,
+    pub quantity_reserved: i64,
+    pub quantity_on_order: i64,
+    pub quantity_in_transit: i64,
+    /// Cents; products without a cost price are valued at zero
+    pub unit_cost: Option<i64>,
+    /// Cents
+    pub total_value: i64,
+}
+
+/// Change of one product between two snapshots
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SnapshotDifference {
+    pub product_id: Uuid,
+    pub base_quantity: i64,
+    pub other_quantity: i64,
+    pub quantity_change: i64,
+    pub base_value: i64,
+    pub other_value: i64,
+    pub value_change: i64,
+}
+
+/// Two snapshots of the same location and scope side by side; only products
+/// whose quantity or value changed are listed
+#[derive(Debug, Clone, Serialize)]
+pub struct SnapshotComparison {
+    pub base: SnapshotRun,
+    pub other: SnapshotRun,
+    pub differences: Vec<SnapshotDifference>,
+    /// Cents
+    pub value_change: i64,
+}
+
+/// Fails unless both snapshots are of the same location and scope
+pub fn ensure_comparable(base: &SnapshotRun, other: &SnapshotRun) -> Result<()> {
+    if base.location_id != other.location_id {
+        return Err(MasterDataError::ValidationError {
+            field: "location_id".to_string(),
+            message: format!(
+                "Snapshot {} is of location {} but snapshot {} is of location {}; only snapshots of one location can be compared",
+                base.id, base.location_id, other.id, other.location_id
+            ),
+        });
+    }
+    if base.scope.clone().normalized() != other.scope.clone().normalized() {
+        return Err(MasterDataError::ValidationError {
+            field: "scope".to_string(),
+            message: format!(
+                "Snapshot {} covers {} but snapshot {} covers {}; only snapshots of the same scope can be compared",
+                base.id,
+                base.scope.describe(),
+                other.id,
+                other.scope.describe()
+            ),
+        });
+    }
+    Ok(())
+}
+
+/// What changed from `base` to `other`
+pub fn compare(
+    base: &SnapshotRun,
+    base_lines: &[SnapshotLine],
+    other: &SnapshotRun,
+    other_lines: &[SnapshotLine],
+) -> Result<SnapshotComparison> {
+    ensure_comparable(base, other)?;
+
+    let mut by_product: BTreeMap<Uuid, SnapshotDifference> = BTreeMap::new();
+    let blank = |product_id| SnapshotDifference {
+        product_id,
+        base_quantity: 0,
+        other_quantity: 0,
+        quantity_change: 0,
+        base_value: 0,
+        other_value: 0,
+        value_change: 0,
+    };
+    for line in base_lines {
+        let difference = by_product.entry(line.product_id).or_insert_with(|| blank(line.product_id));
+        difference.base_quantity += line.quantity_on_hand;
+        difference.base_value += line.total_value;
+    }
+    for line in other_lines {
+        let difference = by_product.entry(line.product_id).or_insert_with(|| blank(line.product_id));
+        difference.other_quantity += line.quantity_on_hand;
+        difference.other_value += line.total_value;
+    }
+
+    let differences: Vec<SnapshotDifference> = by_product
+        .into_values()
+        .map(|mut difference| {
+            difference.quantity_change = difference.other_quantity - difference.base_quantity;
+            difference.value_change = difference.other_value - difference.base_value;
+            difference
+        })
+        .filter(|difference| difference.quantity_change != 0 || difference.value_change != 0)
+        .collect();
+
+    Ok(SnapshotComparison {
+        base: base.clone(),
+        other: other.clone(),
+        value_change: other.total_value - base.total_value,
+        differences,
+    })
+}
+
+/// Month-end snapshot a tenant wants of a location
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotSchedule {
+    pub tenant_id: Uuid,
+    pub location_id: Uuid,
+    pub scope: SnapshotScope,
+    pub enabled: bool,
+    /// Month end the latest scheduled snapshot was taken for
+    pub last_run_on: Option<NaiveDate>,
+    pub updated_by: Option<Uuid>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl SnapshotSchedule {
+    /// Whether the month-end snapshot is to be taken on `today`
+    pub fn is_due(&self, today: NaiveDate) -> bool {
+        self.enabled && today == month_end(today) && self.last_run_on != Some(today)
+    }
+}
+
+/// Last day of the month of `date`
+pub fn month_end(date: NaiveDate) -> NaiveDate {
+    let (year, month) = if date.month() == 12 { (date.year() + 1, 1) } else { (date.year(), date.month() + 1) };
+    NaiveDate::from_ymd_opt(year, month, 1).expect("first of a month is a valid date") - Duration::days(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(abc_class: ABCClassification, unit_cost: i64, quantity: i64) -> SnapshotLine {
+        SnapshotLine {
+            product_id: Uuid::new_v4(),
+            category_id: None,
+            abc_class: Some(abc_class),
+            quantity_on_hand: quantity,
+            quantity_reserved: 0,
+            quantity_on_order: 0,
+            quantity_in_transit: 0,
+            unit_cost: Some(unit_cost),
+            total_value: unit_cost * quantity,
+        }
+    }
+
+    fn run(location_id: Uuid, scope: SnapshotScope, lines: &[SnapshotLine]) -> SnapshotRun {
+        SnapshotRun {
+            id: Uuid::new_v4(),
+            tenant_id: Uuid::from_u128(1),
+            location_id,
+            snapshot_date: NaiveDate::from_ymd_opt(2025, 3, 31).unwrap(),
+            partial: !scope.is_full(),
+            scope,
+            trigger: SnapshotTrigger::Manual,
+            line_count: lines.len() as i64,
+            total_value: lines.iter().map(|line| line.total_value).sum(),
+            created_by: None,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_scope_selects_only_matching_lines() {
+        let stock = [
+            line(ABCClassification::A, 50_000, 4),
+            line(ABCClassification::A, 900, 100),
+            line(ABCClassification::B, 80_000, 2),
+            line(ABCClassification::C, 100, 1_000),
+        ];
+        let class_a = SnapshotScope { abc_class: Some(ABCClassification::A), ..Default::default() };
+        let valuable_a = SnapshotScope { min_unit_value: Some(10_000), ..class_a.clone() };
+        let listed = SnapshotScope { product_ids: vec![stock[2].product_id, stock[3].product_id], ..Default::default() };
+
+        let count = |scope: &SnapshotScope| stock.iter().filter(|line| scope.matches(line)).count();
+        assert_eq!(count(&SnapshotScope::default()), 4);
+        assert_eq!(count(&class_a), 2);
+        assert_eq!(count(&valuable_a), 1);
+        assert_eq!(count(&listed), 2);
+        assert!(!valuable_a.is_full());
+        assert!(SnapshotScope { min_unit_value: Some(-1), ..Default::default() }.validate().is_err());
+        assert!(SnapshotScope { abc_class: Some(ABCClassification::X), ..Default::default() }.validate().is_err());
+    }
+
+    #[test]
+    fn test_snapshots_of_different_scopes_are_not_compared() {
+        let location = Uuid::new_v4();
+        let lines = vec![line(ABCClassification::A, 50_000, 4)];
+        let class_a = SnapshotScope { abc_class: Some(ABCClassification::A), ..Default::default() };
+        let full = run(location, SnapshotScope::default(), &lines);
+        let scoped = run(location, class_a.clone(), &lines);
+
+        match compare(&full, &lines, &scoped, &lines) {
+            Err(MasterDataError::ValidationError { field, message }) => {
+                assert_eq!(field, "scope");
+                assert!(message.contains("the whole location"));
+                assert!(message.contains("ABC class A"));
+            }
+            other => panic!("expected a scope error, got {:?}", other.map(|comparison| comparison.differences)),
+        }
+        assert!(compare(&scoped, &lines, &run(Uuid::new_v4(), class_a.clone(), &lines), &lines).is_err());
+
+        // The same scope with its products listed in another order compares
+        let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+        let first = run(location, SnapshotScope { product_ids: vec![a, b], ..Default::default() }, &lines);
+        let mut later_lines = lines.clone();
+        later_lines[0].quantity_on_hand = 1;
+        later_lines[0].total_value = 50_000;
+        let later = run(location, SnapshotScope { product_ids: vec![b, a, b], ..Default::default() }, &later_lines);
+        let comparison = compare(&first, &lines, &later, &later_lines).unwrap();
+        assert_eq!(comparison.differences.len(), 1);
+        assert_eq!(comparison.differences[0].quantity_change, -3);
+        assert_eq!(comparison.value_change, -150_000);
+    }
+
+    #[test]
+    fn test_month_end_schedule() {
+        let date = |m, d| NaiveDate::from_ymd_opt(2024, m, d).unwrap();
+        assert_eq!(month_end(date(2, 10)), date(2, 29));
+        assert_eq!(month_end(date(12, 1)), date(12, 31));
+
+        let mut schedule = SnapshotSchedule {
+            tenant_id: Uuid::new_v4(),
+            location_id: Uuid::new_v4(),
+            scope: SnapshotScope { abc_class: Some(ABCClassification::A), ..Default::default() },
+            enabled: true,
+            last_run_on: Some(date(1, 31)),
+            updated_by: None,
+            updated_at: Utc::now(),
+        };
+        assert!(!schedule.is_due(date(2, 28)));
+        assert!(schedule.is_due(date(2, 29)));
+        schedule.last_run_on = Some(date(2, 29));
+        assert!(!schedule.is_due(date(2, 29)));
+        assert!(schedule.is_due(date(3, 31)));
+        schedule.enabled = false;
+        assert!(!schedule.is_due(date(3, 31)));
+    }
+}
@@ -0,0 +1,632 @@
+//! # Stocktake Import
+//!
+//! Physical stocktakes are counted with handheld scanners that export one CSV
+//! row per scan:
+//!
+//! ```text
+//! location_code,barcode,counted_quantity,counter_id,scanned_at
+//! WH-01,4006381333931,12,emp-17,2025-01-03T09:14:02Z
+//! ```
+//!
+//! Files are imported into a [`StocktakeSession`]. Every scan line keeps a
+//! checksum so a corrected file can be uploaded again under the same name:
+//! unchanged lines are kept, fixed lines replace the old ones. Scans of the
+//! same product at the same location are summed, and the total is compared
+//! with the system quantity at the session's snapshot time, so sales booked
+//! while counters were walking the aisles do not show up as variance.
+//!
+//! A reviewed session is submitted as one cycle count per location and then
+//! goes through the usual cycle-count approval.
+
+use crate::error::{MasterDataError, Result};
+use crate::inventory::model::{CountStatus, CycleCount};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use uuid::Uuid;
+
+/// Where a session is in review
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StocktakeStatus {
+    /// Accepting uploads
+    Open,
+    /// Handed to cycle-count approval; no further uploads
+    Submitted,
+}
+
+impl StocktakeStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            StocktakeStatus::Open => "open",
+            StocktakeStatus::Submitted => "submitted",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "open" => Some(StocktakeStatus::Open),
+            "submitted" => Some(StocktakeStatus::Submitted),
+            _ => None,
+        }
+    }
+}
+
+/// One row of a scanner file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScanLine {
+    pub file_name: String,
+    pub line_number: usize,
+    pub location_code: String,
+    pub barcode: String,
+    pub counted_quantity: i32,
+    pub counter_id: String,
+    pub scanned_at: DateTime<Utc>,
+    /// Identifies the scan across uploads; see [`parse_scan_file`]
+    pub checksum: String,
+}
+
+/// A row that could not be read
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScanLineError {
+    pub line_number: usize,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ParsedScanFile {
+    pub lines: Vec<ScanLine>,
+    pub errors: Vec<ScanLineError>,
+}
+
+/// A product found by barcode
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CountedProduct {
+    pub product_id: Uuid,
+    pub unit_cost: f64,
+}
+
+/// System stock of one product/location used to rebuild the snapshot quantity
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StockPosition {
+    pub current_quantity: i32,
+    /// Net movements booked after the snapshot
+    pub change_since_snapshot: i32,
+}
+
+impl StockPosition {
+    /// Quantity the system held at the snapshot time
+    pub fn quantity_at_snapshot(&self) -> i32 {
+        self.current_quantity - self.change_since_snapshot
+    }
+}
+
+/// Scans that could not be matched to a product
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UnmatchedBarcode {
+    pub barcode: String,
+    pub location_code: String,
+    pub scan_count: i32,
+    pub counted_quantity: i32,
+    /// `file:line` of each scan, for correcting the source file
+    pub lines: Vec<String>,
+}
+
+/// Total of all scans of one product at one location
+#[derive(Debug, Clone, PartialEq)]
+pub struct CountedItem {
+    pub product_id: Uuid,
+    pub location_id: Uuid,
+    pub location_code: String,
+    pub barcode: String,
+    pub counted_quantity: i32,
+    pub scan_count: i32,
+    pub unit_cost: f64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ScanAggregation {
+    pub items: Vec<CountedItem>,
+    pub unmatched_barcodes: Vec<UnmatchedBarcode>,
+    /// Location codes in the files that are not known locations
+    pub unknown_locations: Vec<String>,
+}
+
+/// Counted against expected quantity of one product at one location
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StocktakeLine {
+    pub product_id: Uuid,
+    pub location_id: Uuid,
+    pub location_code: String,
+    pub barcode: String,
+    pub counted_quantity: i32,
+    /// System quantity at the snapshot time
+    pub system_quantity: i32,
+    pub variance: i32,
+    pub unit_cost: f64,
+    pub variance_value: f64,
+    pub scan_count: i32,
+}
+
+/// Shrinkage and overage of one location
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LocationShrinkage {
+    pub location_id: Uuid,
+    pub location_code: String,
+    pub lines_with_variance: i32,
+    /// Units and value missing compared with the system (positive numbers)
+    pub shrinkage_quantity: i32,
+    pub shrinkage_value: f64,
+    /// Units and value found beyond the system quantity
+    pub overage_quantity: i32,
+    pub overage_value: f64,
+    /// Overage minus shrinkage value
+    pub net_variance_value: f64,
+}
+
+/// A stocktake under review
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StocktakeSession {
+    pub id: Uuid,
+    pub tenant_id: Uuid,
+    pub name: String,
+    /// System quantities are taken as of this time
+    pub snapshot_at: DateTime<Utc>,
+    pub status: StocktakeStatus,
+    pub lines: Vec<StocktakeLine>,
+    pub unmatched_barcodes: Vec<UnmatchedBarcode>,
+    pub unknown_locations: Vec<String>,
+    /// Cycle counts created on submission, by location
+    pub cycle_count_ids: HashMap<Uuid, Uuid>,
+    pub created_by: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub submitted_by: Option<Uuid>,
+    pub submitted_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateStocktakeSessionRequest {
+    pub name: String,
+    pub snapshot_at: DateTime<Utc>,
+}
+
+/// How an upload changed the stored lines of its file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileLineChanges {
+    pub added: i32,
+    pub unchanged: i32,
+    pub removed: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StocktakeImportResult {
+    pub session: StocktakeSession,
+    pub file_name: String,
+    pub changes: FileLineChanges,
+    pub invalid_lines: Vec<ScanLineError>,
+}
+
+/// Parse a scanner CSV.
+///
+/// A header row is skipped, `;` is accepted as the delimiter and timestamps
+/// may be RFC 3339 or `YYYY-MM-DD HH:MM:SS` in UTC. Unreadable rows are
+/// reported in `errors` instead of failing the file. A line's checksum
+/// covers its fields and how often the identical row occurred before it in
+/// the file, so re-uploads match line by line while genuine repeat scans stay
+/// separate.
+pub fn parse_scan_file(file_name: &str, content: &str) -> ParsedScanFile {
+    let mut parsed = ParsedScanFile::default();
+    let delimiter = detect_delimiter(content);
+    let mut occurrences: HashMap<String, usize> = HashMap::new();
+
+    for (index, raw) in content.lines().enumerate() {
+        let line_number = index + 1;
+        let raw = raw.trim();
+        if raw.is_empty() {
+            continue;
+        }
+
+        let fields = split_record(raw, delimiter);
+        if parsed.lines.is_empty() && parsed.errors.is_empty() && is_header(&fields) {
+            continue;
+        }
+
+        match parse_fields(&fields) {
+            Ok((location_code, barcode, counted_quantity, counter_id, scanned_at)) => {
+                let key = format!(
+                    "{}\u{1f}{}\u{1f}{}\u{1f}{}\u{1f}{}",
+                    location_code,
+                    barcode,
+                    counted_quantity,
+                    counter_id,
+                    scanned_at.to_rfc3339()
+                );
+                let occurrence = occurrences.entry(key.clone()).or_insert(0);
+                *occurrence += 1;
+                let checksum = hex_digest(&format!("{}\u{1f}{}", key, occurrence));
+
+                parsed.lines.push(ScanLine {
+                    file_name: file_name.to_string(),
+                    line_number,
+                    location_code,
+                    barcode,
+                    counted_quantity,
+                    counter_id,
+                    scanned_at,
+                    checksum,
+                });
+            }
+            Err(message) => parsed.errors.push(ScanLineError { line_number, message }),
+        }
+    }
+
+    parsed
+}
+
+pub(crate) fn detect_delimiter(content: &str) -> char {
+    let first = content.lines().find(|line| !line.trim().is_empty()).unwrap_or("");
+    if first.contains(';') && !first.contains(',') {
+        ';'
+    } else {
+        ','
+    }
+}
+
+/// Split one record, honouring double-quoted fields
+pub(crate) fn split_record(line: &str, delimiter: char) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            c if c == delimiter && !in_quotes => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+
+    fields.into_iter().map(|f| f.trim().to_string()).collect()
+}
+
+fn is_header(fields: &[String]) -> bool {
+    fields.len() >= 3 && fields[2].parse::<i64>().is_err() && fields.iter().any(|f| f.eq_ignore_ascii_case("barcode"))
+}
+
+type ScanFields = (String, String, i32, String, DateTime<Utc>);
+
+fn parse_fields(fields: &[String]) -> std::result::Result<ScanFields, String> {
+    if fields.len() != 5 {
+        return Err(format!("Expected 5 fields, found {}", fields.len()));
+    }
+
+    let location_code = fields[0].to_uppercase();
+    if location_code.is_empty() {
+        return Err("Location code is empty".to_string());
+    }
+    let barcode = fields[1].clone();
+    if barcode.is_empty() {
+        return Err("Barcode is empty".to_string());
+    }
+    let counted_quantity = fields[2]
+        .parse::<i32>()
+        .ok()
+        .filter(|q| *q >= 0)
+        .ok_or_else(|| format!("Invalid counted quantity '{}'", fields[2]))?;
+    let scanned_at = parse_timestamp(&fields[4]).ok_or_else(|| format!("Invalid timestamp '{}'", fields[4]))?;
+
+    Ok((location_code, barcode, counted_quantity, fields[3].clone(), scanned_at))
+}
+
+fn parse_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|t| t.with_timezone(&Utc))
+        .ok()
+        .or_else(|| NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S").ok().map(|t| t.and_utc()))
+}
+
+fn hex_digest(value: &str) -> String {
+    Sha256::digest(value.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Sum scans per product and location; scans with unknown barcodes or locations are reported instead
+pub fn aggregate_scans(
+    lines: &[ScanLine],
+    products: &HashMap<String, CountedProduct>,
+    locations: &HashMap<String, Uuid>,
+) -> ScanAggregation {
+    let mut items: BTreeMap<(String, Uuid), CountedItem> = BTreeMap::new();
+    let mut unmatched: BTreeMap<(String, String), UnmatchedBarcode> = BTreeMap::new();
+    let mut unknown_locations: Vec<String> = Vec::new();
+
+    for line in lines {
+        let Some(&location_id) = locations.get(&line.location_code) else {
+            if !unknown_locations.contains(&line.location_code) {
+                unknown_locations.push(line.location_code.clone());
+            }
+            continue;
+        };
+
+        match products.get(&line.barcode) {
+            Some(product) => {
+                let item = items
+                    .entry((line.location_code.clone(), product.product_id))
+                    .or_insert_with(|| CountedItem {
+                        product_id: product.product_id,
+                        location_id,
+                        location_code: line.location_code.clone(),
+                        barcode: line.barcode.clone(),
+                        counted_quantity: 0,
+                        scan_count: 0,
+                        unit_cost: product.unit_cost,
+                    });
+                item.counted_quantity += line.counted_quantity;
+                item.scan_count += 1;
+            }
+            None => {
+                let entry = unmatched
+                    .entry((line.barcode.clone(), line.location_code.clone()))
+                    .or_insert_with(|| UnmatchedBarcode {
+                        barcode: line.barcode.clone(),
+                        location_code: line.location_code.clone(),
+                        scan_count: 0,
+                        counted_quantity: 0,
+                        lines: Vec::new(),
+                    });
+                entry.scan_count += 1;
+                entry.counted_quantity += line.counted_quantity;
+                entry.lines.push(format!("{}:{}", line.file_name, line.line_number));
+            }
+        }
+    }
+
+    unknown_locations.sort();
+    ScanAggregation {
+        items: items.into_values().collect(),
+        unmatched_barcodes: unmatched.into_values().collect(),
+        unknown_locations,
+    }
+}
+
+/// Variance of each counted item against the system quantity at the snapshot
+pub fn compute_variances(items: &[CountedItem], positions: &HashMap<(Uuid, Uuid), StockPosition>) -> Vec<StocktakeLine> {
+    items
+        .iter()
+        .map(|item| {
+            let system_quantity = positions
+                .get(&(item.product_id, item.location_id))
+                .map(StockPosition::quantity_at_snapshot)
+                .unwrap_or(0);
+            let variance = item.counted_quantity - system_quantity;
+
+            StocktakeLine {
+                product_id: item.product_id,
+                location_id: item.location_id,
+                location_code: item.location_code.clone(),
+                barcode: item.barcode.clone(),
+                counted_quantity: item.counted_quantity,
+                system_quantity,
+                variance,
+                unit_cost: item.unit_cost,
+                variance_value: variance as f64 * item.unit_cost,
+                scan_count: item.scan_count,
+            }
+        })
+        .collect()
+}
+
+/// Shrinkage and overage per location, largest shrinkage value first
+pub fn shrinkage_by_location(lines: &[StocktakeLine]) -> Vec<LocationShrinkage> {
+    let mut by_location: HashMap<Uuid, LocationShrinkage> = HashMap::new();
+
+    for line in lines {
+        let summary = by_location.entry(line.location_id).or_insert_with(|| LocationShrinkage {
+            location_id: line.location_id,
+            location_code: line.location_code.clone(),
+            lines_with_variance: 0,
+            shrinkage_quantity: 0,
+            shrinkage_value: 0.0,
+            overage_quantity: 0,
+            overage_value: 0.0,
+            net_variance_value: 0.0,
+        });
+
+        if line.variance != 0 {
+            summary.lines_with_variance += 1;
+        }
+        if line.variance < 0 {
+            summary.shrinkage_quantity -= line.variance;
+            summary.shrinkage_value -= line.variance_value;
+        } else {
+            summary.overage_quantity += line.variance;
+            summary.overage_value += line.variance_value;
+        }
+        summary.net_variance_value += line.variance_value;
+    }
+
+    let mut summaries: Vec<LocationShrinkage> = by_location.into_values().collect();
+    summaries.sort_by(|a, b| {
+        b.shrinkage_value
+            .total_cmp(&a.shrinkage_value)
+            .then_with(|| a.location_code.cmp(&b.location_code))
+    });
+    summaries
+}
+
+/// One cycle count per location for the approval workflow; counts with variance need approval
+pub fn cycle_counts_for_session(session: &StocktakeSession, submitted_by: Uuid, now: DateTime<Utc>) -> Vec<CycleCount> {
+    let mut by_location: BTreeMap<Uuid, Vec<&StocktakeLine>> = BTreeMap::new();
+    for line in &session.lines {
+        by_location.entry(line.location_id).or_default().push(line);
+    }
+
+    by_location
+        .into_iter()
+        .map(|(location_id, lines)| {
+            let book_quantity: i32 = lines.iter().map(|l| l.system_quantity).sum();
+            let counted_quantity: i32 = lines.iter().map(|l| l.counted_quantity).sum();
+            let variance_items = lines.iter().filter(|l| l.variance != 0).count() as i32;
+            let variance = counted_quantity - book_quantity;
+            let needs_approval = variance_items > 0;
+            let status = if needs_approval { CountStatus::Reviewed } else { CountStatus::Completed };
+
+            CycleCount {
+                id: Uuid::new_v4(),
+                location_id,
+                count_date: session.snapshot_at.date_naive(),
+                status: status.clone(),
+                total_items: lines.len() as i32,
+                counted_items: lines.len() as i32,
+                variance_items,
+                variance,
+                adjustment_required: needs_approval,
+                adjustment_date: None,
+                adjustment_by: None,
+                approval_required: needs_approval,
+                approved_by: None,
+                approved_date: None,
+                notes: Some(format!("Stocktake '{}' ({})", session.name, session.id)),
+                created_at: now,
+                updated_at: now,
+                created_by: submitted_by,
+                counter_name: format!("Stocktake {}", session.name),
+                book_quantity,
+                counted_quantity,
+                variance_percentage: if book_quantity > 0 {
+                    variance as f64 / book_quantity as f64 * 100.0
+                } else {
+                    0.0
+                },
+                variance_value: lines.iter().map(|l| l.variance_value).sum(),
+                count_status: status,
+                adjustment_applied: false,
+            }
+        })
+        .collect()
+}
+
+/// Uploads are only accepted while the session is open
+pub fn ensure_open(session: &StocktakeSession) -> Result<()> {
+    if session.status != StocktakeStatus::Open {
+        return Err(MasterDataError::ValidationError {
+            field: "session_id".to_string(),
+            message: format!("Stocktake session {} is {}", session.id, session.status.as_str()),
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FILE: &str = "location_code,barcode,counted_quantity,counter_id,scanned_at
+WH-01,4006381333931,12,emp-17,2025-01-03T09:14:02Z
+WH-01,4006381333931,8,emp-17,2025-01-03T09:20:44Z
+wh-01,4006381333931,5,emp-22,2025-01-03 10:01:00
+WH-01,9999999999999,3,emp-22,2025-01-03 10:02:00
+WH-02,4006381333931,7,emp-30,2025-01-03 10:05:00
+WH-01,4006381333931,abc,emp-22,2025-01-03 10:06:00
+";
+
+    fn lookups() -> (HashMap<String, CountedProduct>, HashMap<String, Uuid>, Uuid, Uuid) {
+        let product = CountedProduct { product_id: Uuid::new_v4(), unit_cost: 2.5 };
+        let (wh1, wh2) = (Uuid::new_v4(), Uuid::new_v4());
+        (
+            HashMap::from([("4006381333931".to_string(), product)]),
+            HashMap::from([("WH-01".to_string(), wh1), ("WH-02".to_string(), wh2)]),
+            wh1,
+            wh2,
+        )
+    }
+
+    #[test]
+    fn test_duplicate_scans_are_aggregated() {
+        let parsed = parse_scan_file("aisle-1.csv", FILE);
+        assert_eq!(parsed.lines.len(), 5);
+        assert_eq!(parsed.errors, vec![ScanLineError { line_number: 7, message: "Invalid counted quantity 'abc'".to_string() }]);
+
+        let (products, locations, wh1, wh2) = lookups();
+        let aggregation = aggregate_scans(&parsed.lines, &products, &locations);
+
+        let totals: Vec<(Uuid, i32, i32)> = aggregation
+            .items
+            .iter()
+            .map(|item| (item.location_id, item.counted_quantity, item.scan_count))
+            .collect();
+        assert_eq!(totals, vec![(wh1, 25, 3), (wh2, 7, 1)]);
+    }
+
+    #[test]
+    fn test_unmatched_barcodes_are_reported() {
+        let parsed = parse_scan_file("aisle-1.csv", &format!("{}WH-09,4006381333931,1,emp-1,2025-01-03 11:00:00\n", FILE));
+        let (products, locations, _, _) = lookups();
+        let aggregation = aggregate_scans(&parsed.lines, &products, &locations);
+
+        assert_eq!(
+            aggregation.unmatched_barcodes,
+            vec![UnmatchedBarcode {
+                barcode: "9999999999999".to_string(),
+                location_code: "WH-01".to_string(),
+                scan_count: 1,
+                counted_quantity: 3,
+                lines: vec!["aisle-1.csv:5".to_string()],
+            }]
+        );
+        assert_eq!(aggregation.unknown_locations, vec!["WH-09"]);
+        assert!(aggregation.items.iter().all(|item| item.location_code != "WH-09"));
+    }
+
+    #[test]
+    fn test_variance_uses_snapshot_not_live_quantity() {
+        let (products, locations, wh1, _) = lookups();
+        let product_id = products["4006381333931"].product_id;
+        let parsed = parse_scan_file("aisle-1.csv", "WH-01,4006381333931,100,emp-17,2025-01-03T09:14:02Z\n");
+        let items = aggregate_scans(&parsed.lines, &products, &locations).items;
+
+        // 100 on the shelf at the snapshot; 10 sold while the count was running
+        let positions = HashMap::from([(
+            (product_id, wh1),
+            StockPosition { current_quantity: 90, change_since_snapshot: -10 },
+        )]);
+        let lines = compute_variances(&items, &positions);
+        assert_eq!((lines[0].system_quantity, lines[0].variance), (100, 0));
+
+        // 4 units short at the snapshot
+        let positions = HashMap::from([(
+            (product_id, wh1),
+            StockPosition { current_quantity: 94, change_since_snapshot: -10 },
+        )]);
+        let lines = compute_variances(&items, &positions);
+        assert_eq!((lines[0].system_quantity, lines[0].variance), (104, -4));
+
+        let summary = shrinkage_by_location(&lines);
+        assert_eq!((summary[0].shrinkage_quantity, summary[0].shrinkage_value), (4, 10.0));
+    }
+
+    #[test]
+    fn test_reupload_matches_lines_by_checksum() {
+        let first = parse_scan_file("aisle-1.csv", FILE);
+        let corrected = FILE.replace("WH-01,9999999999999,3", "WH-01,4006381333931,3");
+        let second = parse_scan_file("aisle-1.csv", &corrected);
+
+        let unchanged = first
+            .lines
+            .iter()
+            .filter(|line| second.lines.iter().any(|other| other.checksum == line.checksum))
+            .count();
+        assert_eq!(unchanged, 4);
+
+        // An identical repeat scan is a second line, not a duplicate upload
+        let repeated = parse_scan_file("a.csv", "WH-01,1,1,x,2025-01-03 10:00:00\nWH-01,1,1,x,2025-01-03 10:00:00\n");
+        assert_ne!(repeated.lines[0].checksum, repeated.lines[1].checksum);
+    }
+}
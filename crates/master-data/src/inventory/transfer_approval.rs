@@ -0,0 +1,412 @@
+//! # Stock Transfer Approvals
+//!
+//! Each tenant can require a second person to approve transfers that match
+//! one of its [`TransferApprovalRule`]s:
+//!
+//! - `value_threshold`: the transfer is worth at least `min_value`, valued as
+//!   quantity × the product's current cost
+//! - `region_pair`: the transfer leaves `from_region` for `to_region`; `*`
+//!   stands for any region, and moves within one region never match
+//!
+//! ## Lifecycle
+//!
+//! ```text
+//! pending_approval ─► pending ─► in_transit ─► ...
+//! pending_approval ─► rejected
+//! ```
+//!
+//! A new transfer always reserves its quantity at the source. Without a
+//! matching rule it starts `pending`, released for picking; otherwise it waits
+//! in `pending_approval`. The approver must hold
+//! [`TRANSFER_APPROVAL_PERMISSION`] and must not be the user who created the
+//! transfer. Approval releases it as `pending`; rejection ends it and gives
+//! the reserved stock back.
+
+use crate::error::{MasterDataError, Result};
+use crate::inventory::model::{StockTransfer, TransferStatus};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Permission required to approve or reject a transfer
+pub const TRANSFER_APPROVAL_PERMISSION: &str = "inventory:approve_transfers";
+
+/// Permission required to change a tenant's approval rules
+pub const TRANSFER_RULES_PERMISSION: &str = "settings:write";
+
+/// Matches any region in a [`TransferApprovalRule::RegionPair`]
+pub const ANY_REGION: &str = "*";
+
+/// When a transfer needs approval
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TransferApprovalRule {
+    ValueThreshold { min_value: f64 },
+    RegionPair { from_region: String, to_region: String },
+}
+
+impl TransferApprovalRule {
+    /// Why the rule matches the transfer, or `None`
+    pub fn matches(&self, facts: &TransferFacts) -> Option<String> {
+        match self {
+            TransferApprovalRule::ValueThreshold { min_value } => {
+                let value = facts.value();
+                (value >= *min_value).then(|| format!("value {:.2} is at least {:.2}", value, min_value))
+            }
+            TransferApprovalRule::RegionPair { from_region, to_region } => {
+                let (from, to) = (facts.from_region.as_deref()?, facts.to_region.as_deref()?);
+                let region_matches = |rule: &str, actual: &str| rule == ANY_REGION || rule.eq_ignore_ascii_case(actual);
+                (!from.eq_ignore_ascii_case(to) && region_matches(from_region, from) && region_matches(to_region, to))
+                    .then(|| format!("moves from region {} to region {}", from, to))
+            }
+        }
+    }
+}
+
+/// What the rules look at
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransferFacts {
+    pub quantity: i32,
+    /// Current cost of one unit of the product
+    pub unit_cost: f64,
+    pub from_region: Option<String>,
+    pub to_region: Option<String>,
+}
+
+impl TransferFacts {
+    pub fn value(&self) -> f64 {
+        self.quantity as f64 * self.unit_cost
+    }
+}
+
+/// Reasons of every rule the transfer matches; empty if it needs no approval
+pub fn matching_rules(rules: &[TransferApprovalRule], facts: &TransferFacts) -> Vec<String> {
+    rules.iter().filter_map(|rule| rule.matches(facts)).collect()
+}
+
+pub fn validate_rules(rules: &[TransferApprovalRule]) -> Result<()> {
+    for (index, rule) in rules.iter().enumerate() {
+        let field = format!("rules[{}]", index);
+        match rule {
+            TransferApprovalRule::ValueThreshold { min_value } if !min_value.is_finite() || *min_value < 0.0 => {
+                return Err(MasterDataError::ValidationError {
+                    field,
+                    message: "min_value must be a non-negative amount".to_string(),
+                });
+            }
+            TransferApprovalRule::RegionPair { from_region, to_region }
+                if from_region.trim().is_empty() || to_region.trim().is_empty() =>
+            {
+                return Err(MasterDataError::ValidationError {
+                    field,
+                    message: format!("from_region and to_region are required; use {} for any region", ANY_REGION),
+                });
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApprovalDecision {
+    Approved,
+    Rejected,
+}
+
+impl ApprovalDecision {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ApprovalDecision::Approved => "approved",
+            ApprovalDecision::Rejected => "rejected",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "approved" => Some(ApprovalDecision::Approved),
+            "rejected" => Some(ApprovalDecision::Rejected),
+            _ => None,
+        }
+    }
+}
+
+/// Why a transfer waits for approval, and what was decided
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransferApproval {
+    pub transfer_id: Uuid,
+    pub tenant_id: Uuid,
+    /// One entry per matching rule
+    pub reasons: Vec<String>,
+    pub transfer_value: f64,
+    pub requested_by: Uuid,
+    pub requested_at: DateTime<Utc>,
+    pub escalated_at: Option<DateTime<Utc>>,
+    pub decision: Option<ApprovalDecision>,
+    pub decided_by: Option<Uuid>,
+    pub decided_at: Option<DateTime<Utc>>,
+    pub comment: Option<String>,
+}
+
+impl TransferApproval {
+    /// Undecided and waiting longer than `escalate_after` without having been escalated
+    pub fn needs_escalation(&self, now: DateTime<Utc>, escalate_after: Duration) -> bool {
+        self.decision.is_none() && self.escalated_at.is_none() && now - self.requested_at >= escalate_after
+    }
+}
+
+/// A transfer with its approval, if it needed one
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferRecord {
+    #[serde(flatten)]
+    pub transfer: StockTransfer,
+    pub approval: Option<TransferApproval>,
+}
+
+/// Which transfers to list
+#[derive(Debug, Clone, Default)]
+pub struct TransferListFilter {
+    pub status: Option<TransferStatus>,
+    /// Only transfers waiting for approval that this user may decide, i.e. did not create
+    pub awaiting_approval_by: Option<Uuid>,
+}
+
+/// Stock held at one location, for a transfer or serial units; positive reserves, negative releases
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReservationChange {
+    pub product_id: Uuid,
+    pub location_id: Uuid,
+    pub quantity: i32,
+}
+
+/// Put a new transfer in its first status and reserve its quantity at the
+/// source; `reasons` are the rules it matched
+pub fn open_transfer(
+    transfer: &mut StockTransfer,
+    reasons: Vec<String>,
+    transfer_value: f64,
+    tenant_id: Uuid,
+    now: DateTime<Utc>,
+) -> (Option<TransferApproval>, ReservationChange) {
+    let reservation = ReservationChange {
+        product_id: transfer.product_id,
+        location_id: transfer.from_location_id,
+        quantity: transfer.quantity,
+    };
+    if reasons.is_empty() {
+        transfer.status = TransferStatus::Pending;
+        return (None, reservation);
+    }
+
+    transfer.status = TransferStatus::PendingApproval;
+    let approval = TransferApproval {
+        transfer_id: transfer.id,
+        tenant_id,
+        reasons,
+        transfer_value,
+        requested_by: transfer.created_by,
+        requested_at: now,
+        escalated_at: None,
+        decision: None,
+        decided_by: None,
+        decided_at: None,
+        comment: None,
+    };
+    (Some(approval), reservation)
+}
+
+/// Approve or reject a transfer waiting for approval; a rejection returns the
+/// reservation to release
+pub fn decide(
+    transfer: &mut StockTransfer,
+    approval: &mut TransferApproval,
+    decision: ApprovalDecision,
+    decided_by: Uuid,
+    comment: Option<String>,
+    now: DateTime<Utc>,
+) -> Result<Option<ReservationChange>> {
+    let next = match decision {
+        ApprovalDecision::Approved => TransferStatus::Pending,
+        ApprovalDecision::Rejected => TransferStatus::Rejected,
+    };
+    if transfer.status != TransferStatus::PendingApproval || approval.decision.is_some() {
+        return Err(MasterDataError::InvalidTransferTransition {
+            transfer_id: transfer.id.to_string(),
+            from: transfer.status.as_str().to_string(),
+            to: next.as_str().to_string(),
+        });
+    }
+    if decided_by == transfer.created_by || decided_by == approval.requested_by {
+        return Err(MasterDataError::PermissionDenied {
+            action: "approve a transfer you created".to_string(),
+        });
+    }
+    let comment = comment.filter(|c| !c.trim().is_empty());
+    if decision == ApprovalDecision::Rejected && comment.is_none() {
+        return Err(MasterDataError::ValidationError {
+            field: "comment".to_string(),
+            message: "A rejection needs a comment".to_string(),
+        });
+    }
+
+    transfer.status = next;
+    approval.decision = Some(decision);
+    approval.decided_by = Some(decided_by);
+    approval.decided_at = Some(now);
+    approval.comment = comment;
+
+    match decision {
+        ApprovalDecision::Approved => {
+            transfer.approved_by = Some(decided_by);
+            transfer.approved_date = Some(now);
+            Ok(None)
+        }
+        ApprovalDecision::Rejected => Ok(Some(ReservationChange {
+            product_id: transfer.product_id,
+            location_id: transfer.from_location_id,
+            quantity: -transfer.quantity,
+        })),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inventory::model::TransferPriority;
+
+    fn transfer(created_by: Uuid, quantity: i32) -> StockTransfer {
+        let now = Utc::now();
+        StockTransfer {
+            id: Uuid::new_v4(),
+            product_id: Uuid::new_v4(),
+            from_location_id: Uuid::new_v4(),
+            to_location_id: Uuid::new_v4(),
+            quantity,
+            quantity_shipped: None,
+            quantity_received: None,
+            status: TransferStatus::Requested,
+            priority: TransferPriority::Normal,
+            reason: "Rebalance".to_string(),
+            requested_by: created_by,
+            approved_by: None,
+            shipped_by: None,
+            received_by: None,
+            requested_date: now,
+            approved_date: None,
+            shipped_date: None,
+            received_date: None,
+            actual_delivery_date: None,
+            expected_arrival_date: None,
+            tracking_number: None,
+            carrier: None,
+            shipping_cost: None,
+            notes: None,
+            created_at: now,
+            created_by,
+        }
+    }
+
+    fn facts(quantity: i32, unit_cost: f64, from: &str, to: &str) -> TransferFacts {
+        TransferFacts {
+            quantity,
+            unit_cost,
+            from_region: Some(from.to_string()),
+            to_region: Some(to.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_threshold_and_region_rules() {
+        let rules = vec![
+            TransferApprovalRule::ValueThreshold { min_value: 10_000.0 },
+            TransferApprovalRule::RegionPair {
+                from_region: "EU".to_string(),
+                to_region: ANY_REGION.to_string(),
+            },
+        ];
+
+        assert!(matching_rules(&rules, &facts(99, 100.0, "US", "US")).is_empty());
+        assert_eq!(matching_rules(&rules, &facts(100, 100.0, "US", "US")), ["value 10000.00 is at least 10000.00"]);
+        assert_eq!(matching_rules(&rules, &facts(1, 5.0, "eu", "US")), ["moves from region eu to region US"]);
+        assert!(matching_rules(&rules, &facts(1, 5.0, "EU", "eu")).is_empty(), "same region");
+        assert_eq!(matching_rules(&rules, &facts(500, 40.0, "EU", "APAC")).len(), 2);
+
+        let unknown_regions = TransferFacts {
+            from_region: None,
+            ..facts(1, 5.0, "EU", "US")
+        };
+        assert!(matching_rules(&rules, &unknown_regions).is_empty());
+
+        assert!(validate_rules(&rules).is_ok());
+        assert!(validate_rules(&[TransferApprovalRule::ValueThreshold { min_value: -1.0 }]).is_err());
+        let blank = TransferApprovalRule::RegionPair {
+            from_region: " ".to_string(),
+            to_region: "US".to_string(),
+        };
+        assert!(validate_rules(&[blank]).is_err());
+    }
+
+    #[test]
+    fn test_creator_cannot_approve() {
+        let (clerk, manager) = (Uuid::new_v4(), Uuid::new_v4());
+        let now = Utc::now();
+        let mut t = transfer(clerk, 20);
+        let (approval, reservation) = open_transfer(&mut t, vec!["value".to_string()], 20_000.0, Uuid::new_v4(), now);
+        let mut approval = approval.expect("needs approval");
+        assert_eq!(t.status, TransferStatus::PendingApproval);
+        assert_eq!(reservation.quantity, 20);
+
+        let own = decide(&mut t, &mut approval, ApprovalDecision::Approved, clerk, None, now).unwrap_err();
+        assert!(matches!(own, MasterDataError::PermissionDenied { .. }));
+        assert_eq!(t.status, TransferStatus::PendingApproval);
+
+        assert_eq!(decide(&mut t, &mut approval, ApprovalDecision::Approved, manager, None, now).unwrap(), None);
+        assert_eq!(t.status, TransferStatus::Pending);
+        assert_eq!(t.approved_by, Some(manager));
+
+        let again = decide(&mut t, &mut approval, ApprovalDecision::Rejected, manager, Some("no".to_string()), now);
+        assert!(matches!(again, Err(MasterDataError::InvalidTransferTransition { .. })));
+
+        let mut plain = transfer(clerk, 5);
+        let (none, _) = open_transfer(&mut plain, Vec::new(), 50.0, Uuid::new_v4(), now);
+        assert!(none.is_none());
+        assert_eq!(plain.status, TransferStatus::Pending);
+    }
+
+    #[test]
+    fn test_rejection_releases_reservation() {
+        let (clerk, manager) = (Uuid::new_v4(), Uuid::new_v4());
+        let now = Utc::now();
+        let mut t = transfer(clerk, 12);
+        let (approval, reserved) = open_transfer(&mut t, vec!["region".to_string()], 600.0, Uuid::new_v4(), now);
+        let mut approval = approval.unwrap();
+
+        let silent = decide(&mut t, &mut approval, ApprovalDecision::Rejected, manager, Some("  ".to_string()), now);
+        assert!(matches!(silent, Err(MasterDataError::ValidationError { .. })));
+
+        let released = decide(&mut t, &mut approval, ApprovalDecision::Rejected, manager, Some("Not this quarter".to_string()), now)
+            .unwrap()
+            .expect("rejection releases stock");
+        assert_eq!(t.status, TransferStatus::Rejected);
+        assert_eq!((released.product_id, released.location_id), (reserved.product_id, reserved.location_id));
+        assert_eq!(released.quantity + reserved.quantity, 0);
+        assert_eq!(approval.comment.as_deref(), Some("Not this quarter"));
+
+        assert!(!approval.needs_escalation(now + Duration::days(3), Duration::hours(24)), "decided");
+    }
+
+    #[test]
+    fn test_escalation_once_after_timeout() {
+        let now = Utc::now();
+        let mut t = transfer(Uuid::new_v4(), 1);
+        let (approval, _) = open_transfer(&mut t, vec!["value".to_string()], 1.0, Uuid::new_v4(), now);
+        let mut approval = approval.unwrap();
+        let after = Duration::hours(24);
+
+        assert!(!approval.needs_escalation(now + Duration::hours(23), after));
+        assert!(approval.needs_escalation(now + Duration::hours(24), after));
+        approval.escalated_at = Some(now + Duration::hours(24));
+        assert!(!approval.needs_escalation(now + Duration::hours(48), after));
+    }
+}
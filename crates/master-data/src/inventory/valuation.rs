@@ -0,0 +1,525 @@
+//! # Stock Valuation Reports
+//!
+//! The valuation, KPI, margin and dashboard reports of the stock on hand.
+//! Stock is valued at the product's cost price, and at its base price for the
+//! retail value, each in the product's own currency. Every total is stated in
+//! the tenant's base currency through a
+//! [`CurrencyConversion`] for the valuation date: totals are added up per
+//! currency and each currency's total is converted once, so a total and the
+//! sum of its converted lines can differ by rounding. The original amounts
+//! and applied rates are part of every total.
+//!
+//! Products without a cost price are valued at zero and listed, rather than
+//! left out unnoticed. Consignment stock belongs to its supplier and is left
+//! out of every report ([`own_stock`]).
+//!
+//! The functions here are pure; the stock is read through
+//! [`InventoryValuationRepository`](super::repository::InventoryValuationRepository)
+//! and the rates resolved in
+//! [`InventoryValuationService`](super::service::InventoryValuationService).
+
+use crate::currency::{ConvertedTotal, CurrencyConversion, MoneyTotals};
+use crate::error::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+/// Products listed on the dashboard
+pub const DASHBOARD_TOP_PRODUCTS: usize = 10;
+
+/// On-hand stock of a product at a location, with the product's prices
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StockValueLine {
+    pub product_id: Uuid,
+    pub sku: String,
+    pub name: String,
+    pub category_id: Option<Uuid>,
+    pub location_id: Uuid,
+    pub location_name: String,
+    pub quantity: i64,
+    /// Part of `quantity` owned by suppliers (consignment stock)
+    pub consignment_quantity: i64,
+    /// Minor units of `currency`; `None` if the product has no cost price
+    pub unit_cost: Option<i64>,
+    pub unit_price: i64,
+    pub currency: String,
+}
+
+/// The lines reduced to our own stock: consignment units are the supplier's
+/// and are not valued, lines left without own stock are dropped
+pub fn own_stock(lines: Vec<StockValueLine>) -> Vec<StockValueLine> {
+    lines
+        .into_iter()
+        .filter_map(|mut line| {
+            line.quantity -= line.consignment_quantity.clamp(0, line.quantity);
+            line.consignment_quantity = 0;
+            (line.quantity > 0).then_some(line)
+        })
+        .collect()
+}
+
+/// Currencies the lines are priced in, each once
+pub fn currencies(lines: &[StockValueLine]) -> Vec<String> {
+    let mut currencies: Vec<String> = lines.iter().map(|line| line.currency.clone()).collect();
+    currencies.sort();
+    currencies.dedup();
+    currencies
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ValuedLine {
+    pub product_id: Uuid,
+    pub sku: String,
+    pub name: String,
+    pub location_id: Uuid,
+    pub quantity: i64,
+    pub currency: String,
+    pub unit_cost: Option<i64>,
+    /// In `currency`
+    pub value: i64,
+    /// In the base currency
+    pub converted_value: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StockValuation {
+    pub conversion: CurrencyConversion,
+    pub total_value: ConvertedTotal,
+    pub lines: Vec<ValuedLine>,
+    /// Valued at zero
+    pub uncosted_products: Vec<Uuid>,
+}
+
+fn uncosted_products(lines: &[StockValueLine]) -> Vec<Uuid> {
+    let mut products: Vec<Uuid> = lines
+        .iter()
+        .filter(|line| line.unit_cost.is_none())
+        .map(|line| line.product_id)
+        .collect();
+    products.sort();
+    products.dedup();
+    products
+}
+
+/// Cost value of each line and of the whole stock
+pub fn value_stock(lines: &[StockValueLine], conversion: &CurrencyConversion) -> Result<StockValuation> {
+    let mut totals = MoneyTotals::new();
+    let mut valued = Vec::with_capacity(lines.len());
+    for line in lines {
+        let value = line.unit_cost.unwrap_or(0).saturating_mul(line.quantity);
+        totals.add(&line.currency, value);
+        valued.push(ValuedLine {
+            product_id: line.product_id,
+            sku: line.sku.clone(),
+            name: line.name.clone(),
+            location_id: line.location_id,
+            quantity: line.quantity,
+            currency: line.currency.clone(),
+            unit_cost: line.unit_cost,
+            value,
+            converted_value: conversion.convert(value, &line.currency)?,
+        });
+    }
+
+    Ok(StockValuation {
+        conversion: conversion.clone(),
+        total_value: conversion.total(&totals)?,
+        lines: valued,
+        uncosted_products: uncosted_products(lines),
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StockKpis {
+    pub conversion: CurrencyConversion,
+    pub locations: usize,
+    pub products: usize,
+    pub units: i64,
+    /// At cost
+    pub stock_value: ConvertedTotal,
+    /// At base price
+    pub retail_value: ConvertedTotal,
+    /// Retail less cost value of the stock with a cost price
+    pub potential_margin: ConvertedTotal,
+    /// Potential margin in percent of the retail value of the same stock,
+    /// both in the base currency
+    pub margin_percent: Option<f64>,
+    pub uncosted_products: usize,
+}
+
+/// Headline figures of the stock on hand
+pub fn stock_kpis(lines: &[StockValueLine], conversion: &CurrencyConversion) -> Result<StockKpis> {
+    let mut cost = MoneyTotals::new();
+    let mut retail = MoneyTotals::new();
+    let mut costed_retail = MoneyTotals::new();
+    let mut margin = MoneyTotals::new();
+    for line in lines {
+        let retail_value = line.unit_price.saturating_mul(line.quantity);
+        retail.add(&line.currency, retail_value);
+        if let Some(unit_cost) = line.unit_cost {
+            let cost_value = unit_cost.saturating_mul(line.quantity);
+            cost.add(&line.currency, cost_value);
+            costed_retail.add(&line.currency, retail_value);
+            margin.add(&line.currency, retail_value.saturating_sub(cost_value));
+        }
+    }
+
+    let potential_margin = conversion.total(&margin)?;
+    let costed_retail = conversion.total(&costed_retail)?;
+    let mut locations: Vec<Uuid> = lines.iter().map(|line| line.location_id).collect();
+    locations.sort();
+    locations.dedup();
+    let mut products: Vec<Uuid> = lines.iter().map(|line| line.product_id).collect();
+    products.sort();
+    products.dedup();
+
+    Ok(StockKpis {
+        conversion: conversion.clone(),
+        locations: locations.len(),
+        products: products.len(),
+        units: lines.iter().map(|line| line.quantity).sum(),
+        stock_value: conversion.total(&cost)?,
+        retail_value: conversion.total(&retail)?,
+        margin_percent: (costed_retail.amount != 0)
+            .then(|| potential_margin.amount as f64 / costed_retail.amount as f64 * 100.0),
+        potential_margin,
+        uncosted_products: uncosted_products(lines).len(),
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProductMargin {
+    pub product_id: Uuid,
+    pub sku: String,
+    pub name: String,
+    pub currency: String,
+    /// Per unit, in `currency`
+    pub unit_price: i64,
+    pub unit_cost: i64,
+    pub unit_margin: i64,
+    pub margin_percent: Option<f64>,
+    /// On hand at every location
+    pub quantity: i64,
+    /// Per unit, in the base currency
+    pub converted_unit_price: i64,
+    pub converted_unit_cost: i64,
+    pub converted_unit_margin: i64,
+    /// Margin of the stock on hand, in the base currency
+    pub converted_stock_margin: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MarginReport {
+    pub conversion: CurrencyConversion,
+    /// Highest stock margin first
+    pub products: Vec<ProductMargin>,
+    pub total_stock_margin: ConvertedTotal,
+    /// Left out: without a cost price there is no margin
+    pub uncosted_products: Vec<Uuid>,
+}
+
+/// Margin per product at base price over cost, for a unit and for the stock on hand
+pub fn product_margins(lines: &[StockValueLine], conversion: &CurrencyConversion) -> Result<MarginReport> {
+    let mut by_product: BTreeMap<Uuid, (&StockValueLine, i64)> = BTreeMap::new();
+    for line in lines.iter().filter(|line| line.unit_cost.is_some()) {
+        by_product.entry(line.product_id).or_insert((line, 0)).1 += line.quantity;
+    }
+
+    let mut totals = MoneyTotals::new();
+    let mut products = Vec::with_capacity(by_product.len());
+    for (line, quantity) in by_product.into_values() {
+        let unit_cost = line.unit_cost.unwrap_or(0);
+        let unit_margin = line.unit_price - unit_cost;
+        let stock_margin = unit_margin.saturating_mul(quantity);
+        totals.add(&line.currency, stock_margin);
+        products.push(ProductMargin {
+            product_id: line.product_id,
+            sku: line.sku.clone(),
+            name: line.name.clone(),
+            currency: line.currency.clone(),
+            unit_price: line.unit_price,
+            unit_cost,
+            unit_margin,
+            margin_percent: (line.unit_price != 0).then(|| unit_margin as f64 / line.unit_price as f64 * 100.0),
+            quantity,
+            converted_unit_price: conversion.convert(line.unit_price, &line.currency)?,
+            converted_unit_cost: conversion.convert(unit_cost, &line.currency)?,
+            converted_unit_margin: conversion.convert(unit_margin, &line.currency)?,
+            converted_stock_margin: conversion.convert(stock_margin, &line.currency)?,
+        });
+    }
+    products.sort_by(|a, b| b.converted_stock_margin.cmp(&a.converted_stock_margin).then_with(|| a.sku.cmp(&b.sku)));
+
+    Ok(MarginReport {
+        conversion: conversion.clone(),
+        products,
+        total_stock_margin: conversion.total(&totals)?,
+        uncosted_products: uncosted_products(lines),
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LocationValue {
+    pub location_id: Uuid,
+    pub location_name: String,
+    pub value: ConvertedTotal,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProductValue {
+    pub product_id: Uuid,
+    pub sku: String,
+    pub name: String,
+    pub quantity: i64,
+    /// At cost, in the base currency
+    pub converted_value: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ValuationDashboard {
+    pub conversion: CurrencyConversion,
+    pub stock_value: ConvertedTotal,
+    /// Highest value first
+    pub by_location: Vec<LocationValue>,
+    /// The [`DASHBOARD_TOP_PRODUCTS`] most valuable products
+    pub top_products: Vec<ProductValue>,
+}
+
+/// Stock value at cost per location and of the most valuable products
+pub fn valuation_dashboard(lines: &[StockValueLine], conversion: &CurrencyConversion) -> Result<ValuationDashboard> {
+    let mut total = MoneyTotals::new();
+    let mut locations: BTreeMap<Uuid, (String, MoneyTotals)> = BTreeMap::new();
+    let mut products: BTreeMap<Uuid, ProductValue> = BTreeMap::new();
+    for line in lines {
+        let value = line.unit_cost.unwrap_or(0).saturating_mul(line.quantity);
+        total.add(&line.currency, value);
+        locations
+            .entry(line.location_id)
+            .or_insert_with(|| (line.location_name.clone(), MoneyTotals::new()))
+            .1
+            .add(&line.currency, value);
+        let product = products.entry(line.product_id).or_insert_with(|| ProductValue {
+            product_id: line.product_id,
+            sku: line.sku.clone(),
+            name: line.name.clone(),
+            quantity: 0,
+            converted_value: 0,
+        });
+        product.quantity += line.quantity;
+        product.converted_value = product
+            .converted_value
+            .saturating_add(conversion.convert(value, &line.currency)?);
+    }
+
+    let mut by_location = locations
+        .into_iter()
+        .map(|(location_id, (location_name, totals))| {
+            Ok(LocationValue {
+                location_id,
+                location_name,
+                value: conversion.total(&totals)?,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    by_location.sort_by(|a, b| b.value.amount.cmp(&a.value.amount).then_with(|| a.location_name.cmp(&b.location_name)));
+
+    let mut top_products: Vec<ProductValue> = products.into_values().collect();
+    top_products.sort_by(|a, b| b.converted_value.cmp(&a.converted_value).then_with(|| a.sku.cmp(&b.sku)));
+    top_products.truncate(DASHBOARD_TOP_PRODUCTS);
+
+    Ok(ValuationDashboard {
+        conversion: conversion.clone(),
+        stock_value: conversion.total(&total)?,
+        by_location,
+        top_products,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::currency::{AppliedRate, RateMethod, RateSource};
+    use crate::error::MasterDataError;
+    use chrono::NaiveDate;
+    use rust_decimal::Decimal;
+    use std::str::FromStr;
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    fn to_eur(from: &str, rate: &str) -> (String, AppliedRate) {
+        (
+            from.to_string(),
+            AppliedRate {
+                from_currency: from.to_string(),
+                to_currency: "EUR".to_string(),
+                rate: Decimal::from_str(rate).unwrap(),
+                effective_date: date("2025-03-31"),
+                source: RateSource::Manual,
+                method: RateMethod::Direct,
+            },
+        )
+    }
+
+    /// EUR base with CHF at 1.05 and USD at 0.92
+    fn conversion(lines: &[StockValueLine]) -> CurrencyConversion {
+        let rates = BTreeMap::from([to_eur("CHF", "1.05"), to_eur("USD", "0.92")]);
+        CurrencyConversion::new("EUR", date("2025-03-31"), currencies(lines).iter().map(String::as_str), rates).unwrap()
+    }
+
+    fn line(sku: &str, location: Uuid, quantity: i64, unit_cost: Option<i64>, unit_price: i64, currency: &str) -> StockValueLine {
+        StockValueLine {
+            product_id: Uuid::from_u128(sku.bytes().fold(0, |id, b| id.wrapping_mul(31).wrapping_add(b as u128))),
+            sku: sku.to_string(),
+            name: format!("Product {}", sku),
+            category_id: None,
+            location_id: location,
+            location_name: format!("Location {}", &location.to_string()[..4]),
+            quantity,
+            consignment_quantity: 0,
+            unit_cost,
+            unit_price,
+            currency: currency.to_string(),
+        }
+    }
+
+    fn amount_of(total: &ConvertedTotal, currency: &str) -> (i64, i64) {
+        let share = total.by_currency.iter().find(|share| share.currency == currency).unwrap();
+        (share.amount, share.converted_amount)
+    }
+
+    #[test]
+    fn test_mixed_currencies_are_converted_before_they_are_added() {
+        let zurich = Uuid::new_v4();
+        let lines = vec![
+            line("EUR-1", zurich, 10, Some(1_000), 1_500, "EUR"),
+            line("CHF-1", zurich, 20, Some(1_000), 2_000, "CHF"),
+            line("USD-1", zurich, 5, Some(2_000), 2_500, "USD"),
+        ];
+
+        let valuation = value_stock(&lines, &conversion(&lines)).unwrap();
+
+        // 10,000 EUR + 20,000 CHF * 1.05 + 10,000 USD * 0.92, not 40,000 of nothing
+        assert_eq!(valuation.total_value.currency, "EUR");
+        assert_eq!(valuation.total_value.amount, 10_000 + 21_000 + 9_200);
+        assert_eq!(amount_of(&valuation.total_value, "CHF"), (20_000, 21_000));
+        assert_eq!(amount_of(&valuation.total_value, "USD"), (10_000, 9_200));
+        let chf_line = valuation.lines.iter().find(|line| line.sku == "CHF-1").unwrap();
+        assert_eq!((chf_line.value, chf_line.converted_value), (20_000, 21_000));
+    }
+
+    #[test]
+    fn test_uncosted_products_are_valued_at_zero_and_listed() {
+        let location = Uuid::new_v4();
+        let lines = vec![
+            line("COSTED", location, 2, Some(500), 900, "EUR"),
+            line("UNCOSTED", location, 4, None, 800, "CHF"),
+        ];
+
+        let valuation = value_stock(&lines, &conversion(&lines)).unwrap();
+        let kpis = stock_kpis(&lines, &conversion(&lines)).unwrap();
+
+        assert_eq!(valuation.total_value.amount, 1_000);
+        assert_eq!(valuation.uncosted_products, vec![lines[1].product_id]);
+        assert_eq!(kpis.uncosted_products, 1);
+        // Retail counts every product, the margin only those with a cost
+        assert_eq!(kpis.retail_value.amount, 1_800 + 3_360);
+        assert_eq!(kpis.potential_margin.amount, 800);
+        assert_eq!(kpis.margin_percent, Some(800.0 / 1_800.0 * 100.0));
+    }
+
+    #[test]
+    fn test_kpis_add_up_units_and_values_across_locations() {
+        let (north, south) = (Uuid::new_v4(), Uuid::new_v4());
+        let lines = vec![
+            line("A", north, 10, Some(100), 200, "EUR"),
+            line("A", south, 5, Some(100), 200, "EUR"),
+            line("B", south, 10, Some(1_000), 1_200, "CHF"),
+        ];
+
+        let kpis = stock_kpis(&lines, &conversion(&lines)).unwrap();
+
+        assert_eq!((kpis.locations, kpis.products, kpis.units), (2, 2, 25));
+        assert_eq!(kpis.stock_value.amount, 1_500 + 10_500);
+        assert_eq!(kpis.retail_value.amount, 3_000 + 12_600);
+        assert_eq!(kpis.potential_margin.amount, 1_500 + 2_100);
+    }
+
+    #[test]
+    fn test_consignment_stock_is_not_valued() {
+        let (north, south) = (Uuid::new_v4(), Uuid::new_v4());
+        let lines = own_stock(vec![
+            StockValueLine { consignment_quantity: 4, ..line("A", north, 10, Some(100), 200, "EUR") },
+            // All of it the supplier's
+            StockValueLine { consignment_quantity: 5, ..line("A", south, 5, Some(100), 200, "EUR") },
+            line("B", south, 2, Some(1_000), 1_200, "EUR"),
+        ]);
+
+        let valuation = value_stock(&lines, &conversion(&lines)).unwrap();
+        let kpis = stock_kpis(&lines, &conversion(&lines)).unwrap();
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!(valuation.total_value.amount, 6 * 100 + 2 * 1_000);
+        assert_eq!((kpis.locations, kpis.units), (2, 8));
+    }
+
+    #[test]
+    fn test_product_margins_are_ranked_in_the_base_currency() {
+        let location = Uuid::new_v4();
+        let lines = vec![
+            // 300 EUR margin on stock
+            line("EUR", location, 3, Some(100), 200, "EUR"),
+            // 2 * 150 USD = 300 USD = 276 EUR
+            line("USD", location, 2, Some(100), 250, "USD"),
+            // 2 * 150 CHF = 300 CHF = 315 EUR
+            line("CHF", location, 2, Some(100), 250, "CHF"),
+        ];
+
+        let report = product_margins(&lines, &conversion(&lines)).unwrap();
+
+        let order: Vec<&str> = report.products.iter().map(|product| product.sku.as_str()).collect();
+        assert_eq!(order, vec!["CHF", "EUR", "USD"]);
+        let usd = &report.products[2];
+        assert_eq!((usd.unit_margin, usd.converted_unit_margin, usd.converted_stock_margin), (150, 138, 276));
+        assert_eq!(usd.margin_percent, Some(60.0));
+        assert_eq!(report.total_stock_margin.amount, 315 + 300 + 276);
+    }
+
+    #[test]
+    fn test_dashboard_ranks_locations_by_converted_value() {
+        let (eur_store, chf_store) = (Uuid::new_v4(), Uuid::new_v4());
+        let lines = vec![
+            line("A", eur_store, 100, Some(100), 150, "EUR"),
+            line("B", chf_store, 100, Some(100), 150, "CHF"),
+            line("C", chf_store, 1, Some(50), 80, "USD"),
+        ];
+
+        let dashboard = valuation_dashboard(&lines, &conversion(&lines)).unwrap();
+
+        assert_eq!(dashboard.by_location[0].location_id, chf_store);
+        assert_eq!(dashboard.by_location[0].value.amount, 10_500 + 46);
+        assert_eq!(dashboard.by_location[1].value.amount, 10_000);
+        assert_eq!(dashboard.stock_value.amount, 10_000 + 10_500 + 46);
+        assert_eq!(dashboard.top_products[0].sku, "B");
+    }
+
+    #[test]
+    fn test_a_currency_without_a_rate_fails_the_report() {
+        let location = Uuid::new_v4();
+        let lines = vec![
+            line("EUR", location, 1, Some(100), 200, "EUR"),
+            line("GBP", location, 1, Some(100), 200, "GBP"),
+        ];
+        let rates = BTreeMap::from([to_eur("CHF", "1.05")]);
+
+        let error = CurrencyConversion::new("EUR", date("2025-03-31"), currencies(&lines).iter().map(String::as_str), rates)
+            .unwrap_err();
+        assert!(matches!(error, MasterDataError::ExchangeRateMissing { ref currencies, .. } if currencies == "GBP"));
+
+        // A conversion resolved for other currencies refuses the line instead of skipping it
+        let conversion = CurrencyConversion::new("EUR", date("2025-03-31"), ["CHF"], BTreeMap::from([to_eur("CHF", "1.05")]))
+            .unwrap();
+        assert!(matches!(value_stock(&lines, &conversion), Err(MasterDataError::ExchangeRateMissing { .. })));
+    }
+}
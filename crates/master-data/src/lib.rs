@@ -5,6 +5,7 @@ pub mod customer;
 pub mod supplier;
 pub mod product;
 pub mod inventory;
+pub mod currency;
 pub mod location;
 pub mod organization;
 pub mod security;
@@ -13,6 +14,7 @@ pub mod security;
 pub mod types;
 pub mod error;
 pub mod utils;
+mod spans;
 
 // Re-exports for easy access
 pub use customer::{
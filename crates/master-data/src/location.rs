@@ -1,8 +1,45 @@
-// Location module - placeholder for future implementation
-// Will include comprehensive location and geography management functionality
+//! # Location Module
+//!
+//! Physical sites such as warehouses, stores and offices. Location addresses
+//! use the shared [`erp_core::Address`] and are validated under the same
+//! country rules as customer addresses.
 
 pub mod model {
-    // Location data models will be implemented here
+    use chrono::{DateTime, Utc};
+    use erp_core::Address;
+    use serde::{Deserialize, Serialize};
+    use uuid::Uuid;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    pub enum LocationType {
+        Warehouse,
+        Store,
+        Office,
+        Plant,
+        Other,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct Location {
+        pub id: Uuid,
+        pub tenant_id: Uuid,
+        pub code: String,
+        pub name: String,
+        pub location_type: LocationType,
+        pub address: Address,
+        pub is_active: bool,
+        pub created_at: DateTime<Utc>,
+        pub modified_at: DateTime<Utc>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct CreateLocationRequest {
+        pub code: String,
+        pub name: String,
+        pub location_type: LocationType,
+        pub address: Address,
+    }
 }
 
 pub mod repository {
@@ -10,10 +47,99 @@ pub mod repository {
 }
 
 pub mod service {
-    // Location business logic will be implemented here
+    use super::model::CreateLocationRequest;
+    use crate::error::{MasterDataError, Result};
+    use erp_core::Address;
+
+    /// Normalize and validate a location address; returns the normalized address
+    pub fn validate_address(field: &str, address: &Address) -> Result<Address> {
+        address
+            .normalize_and_validate()
+            .map_err(|violations| MasterDataError::invalid_address(field, &violations))
+    }
+
+    /// Check a new location; returns the request with its address normalized
+    pub fn validate_create_request(mut request: CreateLocationRequest) -> Result<CreateLocationRequest> {
+        request.code = request.code.trim().to_uppercase();
+        if request.code.is_empty() || request.code.len() > 50 {
+            return Err(MasterDataError::ValidationError {
+                field: "code".to_string(),
+                message: "Location code must be between 1 and 50 characters".to_string(),
+            });
+        }
+        if request.name.trim().is_empty() {
+            return Err(MasterDataError::ValidationError {
+                field: "name".to_string(),
+                message: "Location name cannot be empty".to_string(),
+            });
+        }
+
+        request.address = validate_address("address", &request.address)?;
+        Ok(request)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::customer::model::CreateAddressRequest;
+        use crate::customer::validation::CustomerValidator;
+        use crate::location::model::LocationType;
+        use crate::types::AddressType;
+
+        fn request(country: &str, region: Option<&str>, postal_code: &str) -> CreateLocationRequest {
+            CreateLocationRequest {
+                code: " whs-1 ".to_string(),
+                name: "Main warehouse".to_string(),
+                location_type: LocationType::Warehouse,
+                address: Address {
+                    country_code: country.to_string(),
+                    lines: vec!["  500  Industrial Way ".to_string()],
+                    city: "Springfield".to_string(),
+                    region: region.map(str::to_string),
+                    postal_code: Some(postal_code.to_string()),
+                    coordinates: None,
+                },
+            }
+        }
+
+        #[test]
+        fn test_create_request_is_normalized() {
+            let request = validate_create_request(request("us", Some("IL"), "62701")).unwrap();
+            assert_eq!(request.code, "WHS-1");
+            assert_eq!(request.address.country_code, "US");
+            assert_eq!(request.address.lines, vec!["500 Industrial Way"]);
+        }
+
+        #[test]
+        fn test_locations_and_customers_reject_the_same_address() {
+            let error = validate_create_request(request("US", None, "6270")).unwrap_err();
+            let MasterDataError::ValidationError { field, message } = error else {
+                panic!("expected a validation error");
+            };
+            assert_eq!(field, "address");
+            assert!(message.contains("address.postal_code_format"));
+            assert!(message.contains("address.region_required"));
+
+            let customer_address = CreateAddressRequest {
+                address_type: AddressType::Shipping,
+                street_line_1: "500 Industrial Way".to_string(),
+                street_line_2: None,
+                city: "Springfield".to_string(),
+                state_province: None,
+                postal_code: "6270".to_string(),
+                country_code: "US".to_string(),
+                coordinates: None,
+                is_primary: None,
+            };
+            let customer_error = CustomerValidator::new()
+                .validate_address("address", &customer_address.postal_address())
+                .unwrap_err();
+            assert_eq!(customer_error.to_string(), format!("Validation error: address: {}", message));
+        }
+    }
 }
 
 #[cfg(feature = "axum")]
 pub mod handlers {
     // Location HTTP handlers will be implemented here
-}
\ No newline at end of file
+}
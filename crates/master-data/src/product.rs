@@ -17,6 +17,21 @@ pub mod model;
 pub mod repository;
 pub mod service;
 pub mod analytics;
+pub mod barcode;
+pub mod barcode_service;
+pub mod bulk_pricing;
+pub mod bulk_pricing_service;
+pub mod price_list;
+pub mod price_list_service;
+pub mod suggestion;
+pub mod suggestion_service;
+pub mod lifecycle;
+pub mod lifecycle_service;
+pub mod integrity;
+pub mod enhancement;
+pub mod quality;
+pub mod quality_service;
+pub mod feed;
 
 #[cfg(feature = "axum")]
 pub mod handlers;
@@ -34,7 +49,9 @@ pub use model::{
 };
 
 pub use repository::{
-    ProductRepository, PostgresProductRepository,
+    ProductRepository, PostgresProductRepository, PriceUpdateRepository, PriceAdjustment, BarcodeRepository,
+    PriceListRepository, PriceContext, ProductSuggestionRepository, ProductLifecycleRepository,
+    ProductGraph, ProductIntegrityRepository, QualityInspectionRepository,
     // Avoid conflicts - don't export pagination types here
 };
 
@@ -45,6 +62,60 @@ pub use service::{
     ReorderRecommendation, StockOptimization,
 };
 
+pub use bulk_pricing::{
+    PriceGuardConfig, PriceGuardViolation, PriceDiffLine, PriceDiffSummary, BulkPriceDiff,
+    BulkPriceUpdateRecord, BulkPriceUpdateStatus, BulkPriceUpdateOutcome,
+    PRICE_GUARD_OVERRIDE_PERMISSION,
+};
+
+pub use feed::{ProductCatalogExport, ProductFeed, ProductFeedFormat};
+
+pub use bulk_pricing_service::{BulkPriceUpdateService, DefaultBulkPriceUpdateService};
+
+pub use barcode::{
+    Symbology, BarcodeAnalysis, Gs1Data, Gs1Element, BarcodeOwner, ValidateBarcodeRequest,
+    BarcodeValidation, BarcodeReport, InvalidBarcode,
+};
+
+pub use barcode_service::{BarcodeService, DefaultBarcodeService};
+
+pub use price_list::{
+    PriceList, PriceListRequest, PriceListLine, LinePricing, PriceLineError, PriceLineImportResult,
+};
+
+pub use price_list_service::{PriceListService, DefaultPriceListService};
+
+pub use suggestion::{
+    ProductSuggestion, SuggestionStatus, SuggestionSource, AutoApplyRule, SuggestionFilter,
+    SuggestionFailure, BulkAcceptOutcome, PRICE_OPTIMIZATION, REORDER_POINT_OPTIMIZATION, LIFECYCLE_STAGE,
+};
+
+pub use suggestion_service::{
+    ProductSuggestionService, DefaultProductSuggestionService, SuggestionTarget, RepositoryProductUpdates,
+};
+
+pub use lifecycle::{
+    LifecycleAutomationMode, LifecycleRules, LifecycleMetrics, LifecycleEvidence, LifecycleTransition,
+    LifecycleCandidate, LifecycleSales, ProductSales, StageChange, StageChangeSource,
+};
+
+pub use lifecycle_service::{
+    LifecycleAutomationService, DefaultLifecycleAutomationService, LifecycleEvaluation, LifecycleRun,
+};
+
+pub use integrity::{ProductIntegrityReport, ProductIntegrityRepair, RULE_INTEGRITY_REPAIR, RULE_PRODUCT_CREATED};
+
+pub use enhancement::{ProductEnhancementJob, ProductEnhancementHandler, PRODUCT_ENHANCEMENT_JOB};
+
+pub use quality::{
+    QualityInspection, InspectionStatus, InspectionOutcome, InspectionResult, BatchDisposition, QualityBatch,
+    InspectionRules, ScheduleInspectionRequest, RescheduleInspectionRequest, QUALITY_INSPECTION_PERMISSION,
+};
+
+pub use quality_service::{
+    QualityInspectionService, DefaultQualityInspectionService, RepositoryQualityEngine, InspectionRun,
+};
+
 pub use analytics::{
     ProductAnalyticsEngine, DefaultProductAnalyticsEngine,
     ProductPerformanceMetrics, MarketIntelligence,
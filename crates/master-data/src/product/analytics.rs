@@ -5,7 +5,7 @@
 
 use chrono::{DateTime, Utc, Duration};
 use serde::{Deserialize, Serialize};
-use sqlx::PgPool;
+use sqlx::{PgPool, Row};
 use std::collections::HashMap;
 use uuid::Uuid;
 use anyhow::Result;
@@ -971,6 +971,38 @@ impl DefaultProductAnalyticsEngine {
     pub fn new(pool: PgPool) -> Self {
         Self { pool }
     }
+
+    /// Units received back on return orders per unit shipped to customers in the period
+    async fn measured_return_rate(
+        &self,
+        product_id: Uuid,
+        period_start: DateTime<Utc>,
+        period_end: DateTime<Utc>,
+    ) -> Result<f64> {
+        let row = sqlx::query(
+            r#"
+            SELECT
+                (SELECT COALESCE(SUM(l.quantity), 0)::BIGINT
+                 FROM public.return_order_lines l
+                 JOIN public.return_orders o ON o.id = l.return_order_id
+                 WHERE l.product_id = $1 AND o.received_at >= $2 AND o.received_at < $3) AS returned,
+                (SELECT COALESCE(-SUM(quantity_change), 0)::BIGINT
+                 FROM inventory_transactions
+                 WHERE product_id = $1 AND transaction_type = 'shipment'
+                   AND transaction_date >= $2 AND transaction_date < $3
+                   AND COALESCE(reference_number, '') NOT LIKE 'RMA-%') AS shipped
+            "#,
+        )
+        .bind(product_id)
+        .bind(period_start)
+        .bind(period_end)
+        .fetch_one(&self.pool)
+        .await?;
+
+        let returned: i64 = row.get("returned");
+        let shipped: i64 = row.get("shipped");
+        Ok(if shipped > 0 { returned as f64 / shipped as f64 } else { 0.0 })
+    }
 }
 
 #[async_trait::async_trait]
@@ -1245,13 +1277,15 @@ impl ProductAnalyticsEngine for DefaultProductAnalyticsEngine {
         period_start: DateTime<Utc>,
         period_end: DateTime<Utc>,
     ) -> Result<QualityAnalytics> {
+        let return_rate = self.measured_return_rate(product_id, period_start, period_end).await?;
+
         // Advanced quality analytics with trend analysis
         let analytics = QualityAnalytics {
             product_id,
             defect_rate: 0.02,
             quality_score: 4.7,
             customer_complaints: 5,
-            return_rate: 0.015,
+            return_rate,
             warranty_claims: 3,
             quality_trends: vec![
                 QualityTrend {
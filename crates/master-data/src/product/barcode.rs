@@ -0,0 +1,582 @@
+//! Barcode validation and normalization
+//!
+//! Barcodes are detected as EAN-13, EAN-8, UPC-A, GS1-128 or plain Code128.
+//! Check digits are verified where the symbology defines one (the GS1 mod-10
+//! digit of EAN/UPC and of GTINs and SSCCs inside GS1-128); Code128 is passed
+//! through since its check character is part of the printed symbol, not the data.
+//!
+//! Values are stored normalized: whitespace and scanner symbology prefixes
+//! (`]C1`, `]E0`, ...) are removed, leading zeros are kept, and GS1-128 is
+//! stored in its bracketed form, e.g. `(01)09501101530003(17)261231(10)AB12`.
+//! GS1 application identifiers are parsed so batch and expiry can prefill
+//! goods receipts.
+
+use chrono::{Datelike, NaiveDate};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Longest barcode value stored on a product or variant
+pub const MAX_BARCODE_LENGTH: usize = 100;
+
+/// FNC1 as sent by scanners: the ASCII group separator
+const GROUP_SEPARATOR: char = '\u{1d}';
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Symbology {
+    Ean13,
+    Ean8,
+    UpcA,
+    #[serde(rename = "gs1_128")]
+    Gs1128,
+    Code128,
+}
+
+/// One application identifier and its data
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Gs1Element {
+    pub ai: String,
+    pub value: String,
+}
+
+/// GS1-128 content, with the fields goods receipt cares about picked out
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Gs1Data {
+    pub elements: Vec<Gs1Element>,
+    /// AI 01
+    pub gtin: Option<String>,
+    /// AI 10
+    pub batch: Option<String>,
+    /// AI 17
+    pub expiry: Option<NaiveDate>,
+    /// AI 15
+    pub best_before: Option<NaiveDate>,
+    /// AI 11
+    pub production_date: Option<NaiveDate>,
+    /// AI 21
+    pub serial: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BarcodeAnalysis {
+    pub input: String,
+    /// Value to store; only meaningful when `valid`
+    pub normalized: String,
+    pub symbology: Option<Symbology>,
+    pub valid: bool,
+    pub errors: Vec<String>,
+    pub gs1: Option<Gs1Data>,
+}
+
+/// Product or variant carrying a barcode
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BarcodeOwner {
+    pub product_id: Uuid,
+    pub variant_id: Option<Uuid>,
+    /// Product SKU, or variant SKU for variants
+    pub sku: String,
+    pub barcode: String,
+}
+
+impl BarcodeOwner {
+    fn is(&self, product_id: Option<Uuid>, variant_id: Option<Uuid>) -> bool {
+        match (variant_id, self.variant_id) {
+            (Some(wanted), Some(own)) => wanted == own,
+            (None, None) => product_id == Some(self.product_id),
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValidateBarcodeRequest {
+    pub barcode: String,
+    /// Validate as this symbology instead of detecting one
+    pub symbology: Option<Symbology>,
+    /// The product or variant the barcode is meant for; it does not conflict with itself
+    pub product_id: Option<Uuid>,
+    pub variant_id: Option<Uuid>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BarcodeValidation {
+    #[serde(flatten)]
+    pub analysis: BarcodeAnalysis,
+    /// Other products or variants of the tenant already using the barcode
+    pub conflicts: Vec<BarcodeOwner>,
+    /// Valid and not used elsewhere
+    pub available: bool,
+}
+
+impl BarcodeValidation {
+    pub fn new(analysis: BarcodeAnalysis, owners: Vec<BarcodeOwner>, product_id: Option<Uuid>, variant_id: Option<Uuid>) -> Self {
+        let conflicts: Vec<_> = owners.into_iter().filter(|o| !o.is(product_id, variant_id)).collect();
+        let available = analysis.valid && conflicts.is_empty();
+        Self { analysis, conflicts, available }
+    }
+}
+
+/// Stored barcode that is invalid or not in normalized form
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InvalidBarcode {
+    pub owner: BarcodeOwner,
+    /// What it would be stored as, if it is valid
+    pub normalized: Option<String>,
+    pub errors: Vec<String>,
+}
+
+/// Existing data that predates validation
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BarcodeReport {
+    pub checked: usize,
+    pub invalid: Vec<InvalidBarcode>,
+    /// Groups of products and variants sharing one barcode
+    pub duplicates: Vec<Vec<BarcodeOwner>>,
+}
+
+impl BarcodeReport {
+    pub fn build(barcodes: Vec<BarcodeOwner>, today: NaiveDate) -> Self {
+        let mut report = BarcodeReport {
+            checked: barcodes.len(),
+            ..Default::default()
+        };
+        let mut by_key: std::collections::BTreeMap<String, Vec<BarcodeOwner>> = Default::default();
+
+        for owner in barcodes {
+            let analysis = analyze(&owner.barcode, None, today);
+            if !analysis.valid {
+                report.invalid.push(InvalidBarcode {
+                    owner: owner.clone(),
+                    normalized: None,
+                    errors: analysis.errors,
+                });
+            } else if analysis.normalized != owner.barcode {
+                report.invalid.push(InvalidBarcode {
+                    owner: owner.clone(),
+                    normalized: Some(analysis.normalized.clone()),
+                    errors: vec!["Barcode is not stored in normalized form".to_string()],
+                });
+            }
+            let key = uniqueness_key(&if analysis.valid { analysis.normalized } else { normalize(&owner.barcode) });
+            by_key.entry(key).or_default().push(owner);
+        }
+
+        report.duplicates = by_key.into_values().filter(|owners| owners.len() > 1).collect();
+        report
+    }
+}
+
+/// Strip whitespace and scanner prefixes; digits are kept as text so leading zeros survive
+pub fn normalize(raw: &str) -> String {
+    // The group separator counts as whitespace in Unicode but is FNC1 in GS1-128
+    let value: String = raw.chars().filter(|c| !c.is_whitespace() || *c == GROUP_SEPARATOR).collect();
+    for prefix in ["]C0", "]E0", "]E4"] {
+        if let Some(rest) = value.strip_prefix(prefix) {
+            return rest.to_string();
+        }
+    }
+    value
+}
+
+/// GS1 mod-10 check digit over `digits` (the value without its check digit)
+pub fn gs1_check_digit(digits: &str) -> Option<u32> {
+    let mut sum = 0;
+    for (i, c) in digits.chars().rev().enumerate() {
+        let digit = c.to_digit(10)?;
+        sum += if i % 2 == 0 { digit * 3 } else { digit };
+    }
+    Some((10 - sum % 10) % 10)
+}
+
+/// Whether the last digit of `value` is its GS1 check digit
+pub fn has_valid_check_digit(value: &str) -> bool {
+    if value.len() < 2 || !value.chars().all(|c| c.is_ascii_digit()) {
+        return false;
+    }
+    let (data, check) = value.split_at(value.len() - 1);
+    gs1_check_digit(data) == check.chars().next().and_then(|c| c.to_digit(10))
+}
+
+/// Key barcodes are unique on per tenant: GTINs are zero-padded to 14 digits
+/// so an UPC-A and the EAN-13 with a leading zero collide. Must match
+/// `barcode_uniqueness_key()` in the database.
+pub fn uniqueness_key(normalized: &str) -> String {
+    let numeric = normalized.chars().all(|c| c.is_ascii_digit());
+    if numeric && matches!(normalized.len(), 8 | 12 | 13 | 14) {
+        format!("{:0>14}", normalized)
+    } else {
+        normalized.to_string()
+    }
+}
+
+/// Detect, validate and normalize a barcode. `expected` checks it against a
+/// symbology chosen by the caller instead of detecting one. `today` anchors the
+/// century of GS1 dates.
+pub fn analyze(raw: &str, expected: Option<Symbology>, today: NaiveDate) -> BarcodeAnalysis {
+    let mut analysis = BarcodeAnalysis {
+        input: raw.to_string(),
+        normalized: String::new(),
+        symbology: None,
+        valid: false,
+        errors: Vec::new(),
+        gs1: None,
+    };
+
+    let value = normalize(raw);
+    if value.is_empty() {
+        analysis.errors.push("Barcode is empty".to_string());
+        return analysis;
+    }
+
+    let symbology = expected.unwrap_or_else(|| detect(&value));
+    analysis.symbology = Some(symbology);
+    let result = match symbology {
+        Symbology::Ean13 => check_gtin(&value, 13, "EAN-13"),
+        Symbology::Ean8 => check_gtin(&value, 8, "EAN-8"),
+        Symbology::UpcA => check_gtin(&value, 12, "UPC-A"),
+        Symbology::Code128 => check_code128(&value),
+        Symbology::Gs1128 => parse_gs1(&value, today).map(|(normalized, data)| {
+            analysis.gs1 = Some(data);
+            normalized
+        }),
+    };
+
+    match result {
+        Ok(normalized) if normalized.chars().count() > MAX_BARCODE_LENGTH => {
+            analysis.errors.push(format!("Barcode is longer than {} characters", MAX_BARCODE_LENGTH));
+        }
+        Ok(normalized) => {
+            analysis.normalized = normalized;
+            analysis.valid = true;
+        }
+        Err(errors) => analysis.errors = errors,
+    }
+    analysis
+}
+
+fn detect(value: &str) -> Symbology {
+    if value.starts_with("]C1") || value.starts_with('(') || value.contains(GROUP_SEPARATOR) {
+        return Symbology::Gs1128;
+    }
+    if value.chars().all(|c| c.is_ascii_digit()) {
+        match value.len() {
+            13 => return Symbology::Ean13,
+            12 => return Symbology::UpcA,
+            8 => return Symbology::Ean8,
+            _ => {}
+        }
+    }
+    Symbology::Code128
+}
+
+fn check_gtin(value: &str, length: usize, name: &str) -> std::result::Result<String, Vec<String>> {
+    if value.len() != length || !value.chars().all(|c| c.is_ascii_digit()) {
+        return Err(vec![format!("{} must be exactly {} digits", name, length)]);
+    }
+    if !has_valid_check_digit(value) {
+        let expected = gs1_check_digit(&value[..length - 1]).unwrap_or_default();
+        return Err(vec![format!("{} check digit is wrong; expected {}", name, expected)]);
+    }
+    Ok(value.to_string())
+}
+
+fn check_code128(value: &str) -> std::result::Result<String, Vec<String>> {
+    // Printable ASCII; whitespace is already gone
+    if value.chars().all(|c| c.is_ascii_graphic()) {
+        Ok(value.to_string())
+    } else {
+        Err(vec!["Code128 barcodes may only contain printable ASCII characters".to_string()])
+    }
+}
+
+#[derive(Clone, Copy)]
+enum AiFormat {
+    Numeric,
+    Alphanumeric,
+    Date,
+}
+
+struct AiSpec {
+    ai: &'static str,
+    /// `Some` for fixed-length data, which needs no FNC1 after it
+    fixed: Option<usize>,
+    max: usize,
+    format: AiFormat,
+    check_digit: bool,
+}
+
+const fn ai(ai: &'static str, fixed: Option<usize>, max: usize, format: AiFormat, check_digit: bool) -> AiSpec {
+    AiSpec { ai, fixed, max, format, check_digit }
+}
+
+/// Application identifiers the ERP interprets
+const AI_SPECS: &[AiSpec] = &[
+    ai("00", Some(18), 18, AiFormat::Numeric, true),
+    ai("01", Some(14), 14, AiFormat::Numeric, true),
+    ai("02", Some(14), 14, AiFormat::Numeric, true),
+    ai("10", None, 20, AiFormat::Alphanumeric, false),
+    ai("11", Some(6), 6, AiFormat::Date, false),
+    ai("12", Some(6), 6, AiFormat::Date, false),
+    ai("13", Some(6), 6, AiFormat::Date, false),
+    ai("15", Some(6), 6, AiFormat::Date, false),
+    ai("16", Some(6), 6, AiFormat::Date, false),
+    ai("17", Some(6), 6, AiFormat::Date, false),
+    ai("20", Some(2), 2, AiFormat::Numeric, false),
+    ai("21", None, 20, AiFormat::Alphanumeric, false),
+    ai("30", None, 8, AiFormat::Numeric, false),
+    ai("37", None, 8, AiFormat::Numeric, false),
+];
+
+fn ai_spec(ai: &str) -> Option<&'static AiSpec> {
+    AI_SPECS.iter().find(|spec| spec.ai == ai)
+}
+
+/// Split a GS1-128 value into elements, from either the bracketed form or
+/// scanner output (`]C1` prefix, FNC1 as group separator)
+fn split_gs1(value: &str) -> std::result::Result<Vec<Gs1Element>, String> {
+    let mut elements = Vec::new();
+
+    if value.starts_with('(') {
+        let mut rest = value;
+        while !rest.is_empty() {
+            let close = rest.find(')').ok_or("Unclosed application identifier bracket")?;
+            let ai = &rest[1..close];
+            let data_end = rest[close + 1..].find('(').map_or(rest.len(), |i| close + 1 + i);
+            elements.push(Gs1Element {
+                ai: ai.to_string(),
+                value: rest[close + 1..data_end].to_string(),
+            });
+            rest = &rest[data_end..];
+        }
+        return Ok(elements);
+    }
+
+    let mut rest = value.strip_prefix("]C1").unwrap_or(value).trim_start_matches(GROUP_SEPARATOR);
+    while !rest.is_empty() {
+        let spec = (2..=4)
+            .filter_map(|len| rest.get(..len))
+            .find_map(ai_spec)
+            .ok_or_else(|| {
+                format!(
+                    "Unknown application identifier at '{}'; send unknown AIs in the bracketed form",
+                    rest.chars().take(4).collect::<String>()
+                )
+            })?;
+        let data = &rest[spec.ai.len()..];
+        let end = match spec.fixed {
+            Some(len) => len.min(data.len()),
+            None => data.find(GROUP_SEPARATOR).unwrap_or(data.len()),
+        };
+        elements.push(Gs1Element {
+            ai: spec.ai.to_string(),
+            value: data[..end].to_string(),
+        });
+        rest = data[end..].trim_start_matches(GROUP_SEPARATOR);
+    }
+    Ok(elements)
+}
+
+fn parse_gs1(value: &str, today: NaiveDate) -> std::result::Result<(String, Gs1Data), Vec<String>> {
+    let elements = split_gs1(value).map_err(|e| vec![e])?;
+    if elements.is_empty() {
+        return Err(vec!["GS1-128 barcode has no application identifiers".to_string()]);
+    }
+
+    let mut data = Gs1Data::default();
+    let mut errors = Vec::new();
+    for element in &elements {
+        let (ai, value) = (element.ai.as_str(), element.value.as_str());
+        if ai.len() < 2 || ai.len() > 4 || !ai.chars().all(|c| c.is_ascii_digit()) {
+            errors.push(format!("'{}' is not an application identifier", ai));
+            continue;
+        }
+        if value.is_empty() {
+            errors.push(format!("AI ({}) has no data", ai));
+            continue;
+        }
+        let Some(spec) = ai_spec(ai) else {
+            // Kept as is, without interpretation
+            continue;
+        };
+
+        let length_ok = match spec.fixed {
+            Some(len) => value.len() == len,
+            None => value.len() <= spec.max,
+        };
+        if !length_ok {
+            errors.push(match spec.fixed {
+                Some(len) => format!("AI ({}) must have {} characters", ai, len),
+                None => format!("AI ({}) may have at most {} characters", ai, spec.max),
+            });
+            continue;
+        }
+        let numeric = value.chars().all(|c| c.is_ascii_digit());
+        match spec.format {
+            AiFormat::Numeric | AiFormat::Date if !numeric => {
+                errors.push(format!("AI ({}) must be numeric", ai));
+                continue;
+            }
+            AiFormat::Alphanumeric if !value.chars().all(|c| c.is_ascii_graphic()) => {
+                errors.push(format!("AI ({}) contains characters GS1 does not allow", ai));
+                continue;
+            }
+            _ => {}
+        }
+        if spec.check_digit && !has_valid_check_digit(value) {
+            errors.push(format!("AI ({}) check digit is wrong", ai));
+            continue;
+        }
+
+        let date = match spec.format {
+            AiFormat::Date => match gs1_date(value, today) {
+                Some(date) => Some(date),
+                None => {
+                    errors.push(format!("AI ({}) is not a valid YYMMDD date", ai));
+                    continue;
+                }
+            },
+            _ => None,
+        };
+        match ai {
+            "01" => data.gtin = Some(value.to_string()),
+            "10" => data.batch = Some(value.to_string()),
+            "11" => data.production_date = date,
+            "15" => data.best_before = date,
+            "17" => data.expiry = date,
+            "21" => data.serial = Some(value.to_string()),
+            _ => {}
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    let normalized = elements.iter().map(|e| format!("({}){}", e.ai, e.value)).collect();
+    data.elements = elements;
+    Ok((normalized, data))
+}
+
+/// GS1 `YYMMDD`; the century is the one putting the year within 49 years back
+/// or 50 ahead of `today`, and day `00` means the last day of the month
+fn gs1_date(value: &str, today: NaiveDate) -> Option<NaiveDate> {
+    let yy: i32 = value.get(0..2)?.parse().ok()?;
+    let month: u32 = value.get(2..4)?.parse().ok()?;
+    let day: u32 = value.get(4..6)?.parse().ok()?;
+
+    let current = today.year();
+    let century = current - current.rem_euclid(100);
+    let difference = yy - current.rem_euclid(100);
+    let year = if difference >= 51 {
+        century - 100 + yy
+    } else if difference <= -50 {
+        century + 100 + yy
+    } else {
+        century + yy
+    };
+
+    if day == 0 {
+        let (next_year, next_month) = if month == 12 { (year + 1, 1) } else { (year, month + 1) };
+        NaiveDate::from_ymd_opt(next_year, next_month, 1)?.pred_opt()
+    } else {
+        NaiveDate::from_ymd_opt(year, month, day)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 10, 16).unwrap()
+    }
+
+    fn check(raw: &str) -> BarcodeAnalysis {
+        analyze(raw, None, today())
+    }
+
+    #[test]
+    fn test_ean13_check_digit() {
+        let ok = check("4006381333931");
+        assert!(ok.valid, "{:?}", ok.errors);
+        assert_eq!(ok.symbology, Some(Symbology::Ean13));
+
+        let wrong = check("4006381333932");
+        assert!(!wrong.valid);
+        assert_eq!(wrong.errors, vec!["EAN-13 check digit is wrong; expected 1".to_string()]);
+    }
+
+    #[test]
+    fn test_ean8_check_digit() {
+        assert!(check("96385074").valid);
+        assert_eq!(check("96385074").symbology, Some(Symbology::Ean8));
+        assert!(!check("96385075").valid);
+    }
+
+    #[test]
+    fn test_upc_a_check_digit_and_leading_zeros() {
+        let upc = check(" 0 36000 29145 2\n");
+        assert!(upc.valid, "{:?}", upc.errors);
+        assert_eq!(upc.symbology, Some(Symbology::UpcA));
+        assert_eq!(upc.normalized, "036000291452");
+        assert!(!check("036000291453").valid);
+    }
+
+    #[test]
+    fn test_expected_symbology_is_enforced() {
+        let short = analyze("036000291452", Some(Symbology::Ean13), today());
+        assert!(!short.valid);
+        assert_eq!(short.errors, vec!["EAN-13 must be exactly 13 digits".to_string()]);
+    }
+
+    #[test]
+    fn test_code128_passthrough() {
+        let code = check("]C0ABC-123/x");
+        assert!(code.valid);
+        assert_eq!(code.symbology, Some(Symbology::Code128));
+        assert_eq!(code.normalized, "ABC-123/x");
+        assert!(!check("ABC\u{7f}").valid);
+    }
+
+    #[test]
+    fn test_gs1_128_scanner_output_is_parsed() {
+        let raw = "]C1010950110153000317261200".to_string() + "10LOT 42A\u{1d}21SN-9";
+        let gs1 = check(&raw);
+        assert!(gs1.valid, "{:?}", gs1.errors);
+        assert_eq!(gs1.symbology, Some(Symbology::Gs1128));
+        assert_eq!(gs1.normalized, "(01)09501101530003(17)261200(10)LOT42A(21)SN-9");
+
+        let data = gs1.gs1.unwrap();
+        assert_eq!(data.gtin.as_deref(), Some("09501101530003"));
+        assert_eq!(data.batch.as_deref(), Some("LOT42A"));
+        assert_eq!(data.serial.as_deref(), Some("SN-9"));
+        // Day 00 is the last day of the month
+        assert_eq!(data.expiry, NaiveDate::from_ymd_opt(2026, 12, 31));
+    }
+
+    #[test]
+    fn test_gs1_128_bracketed_form_and_check_digits() {
+        let gs1 = check("(01)09501101530003(10)B1(15)990101");
+        assert!(gs1.valid, "{:?}", gs1.errors);
+        // 99 is more than 50 years ahead of 2026, so it is 1999
+        assert_eq!(gs1.gs1.unwrap().best_before, NaiveDate::from_ymd_opt(1999, 1, 1));
+
+        let bad_gtin = check("(01)09501101530004(10)B1");
+        assert!(!bad_gtin.valid);
+        assert_eq!(bad_gtin.errors, vec!["AI (01) check digit is wrong".to_string()]);
+
+        let bad_sscc = check("(00)106141412345678900");
+        assert!(!bad_sscc.valid);
+        assert!(check("(00)106141412345678908").valid);
+
+        assert!(!check("(17)261332").valid);
+        assert!(!check("]C199ABC").valid);
+    }
+
+    #[test]
+    fn test_uniqueness_key_pads_gtins() {
+        assert_eq!(uniqueness_key("036000291452"), "00036000291452");
+        assert_eq!(uniqueness_key("0036000291452"), "00036000291452");
+        assert_eq!(uniqueness_key("96385074"), "00000096385074");
+        assert_eq!(uniqueness_key("ABC-1"), "ABC-1");
+    }
+}
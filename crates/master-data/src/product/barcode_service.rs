@@ -0,0 +1,253 @@
+use async_trait::async_trait;
+use chrono::Utc;
+use erp_core::error::{Error, ErrorCode, Result};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::product::barcode::*;
+use crate::product::repository::BarcodeRepository;
+use crate::types::TenantContext;
+
+/// Barcode validation and tenant-wide uniqueness for products and variants
+#[async_trait]
+pub trait BarcodeService: Send + Sync {
+    /// Check format, check digits and uniqueness without writing anything
+    async fn validate_barcode(&self, request: ValidateBarcodeRequest) -> Result<BarcodeValidation>;
+
+    /// Validate and store the normalized barcode of a product, or of one of its
+    /// variants; `None` clears it. Returns the stored value.
+    async fn assign_barcode(&self, product_id: Uuid, variant_id: Option<Uuid>, barcode: Option<String>) -> Result<Option<String>>;
+
+    /// Stored barcodes that are invalid, not normalized or shared
+    async fn barcode_report(&self) -> Result<BarcodeReport>;
+}
+
+pub struct DefaultBarcodeService {
+    repository: Arc<dyn BarcodeRepository>,
+    tenant_context: TenantContext,
+}
+
+impl DefaultBarcodeService {
+    pub fn new(repository: Arc<dyn BarcodeRepository>, tenant_context: TenantContext) -> Self {
+        Self {
+            repository,
+            tenant_context,
+        }
+    }
+}
+
+/// The normalized barcode, or a `ValidationFailed` error listing what is wrong
+pub fn require_valid(analysis: &BarcodeAnalysis) -> Result<String> {
+    if analysis.valid {
+        Ok(analysis.normalized.clone())
+    } else {
+        Err(Error::new(
+            ErrorCode::ValidationFailed,
+            format!("Invalid barcode {}: {}", analysis.input.trim(), analysis.errors.join("; ")),
+        ))
+    }
+}
+
+/// Like [`require_valid`], and a `ConflictError` if another product or variant has the barcode
+pub fn require_available(validation: &BarcodeValidation) -> Result<String> {
+    let barcode = require_valid(&validation.analysis)?;
+    if let Some(owner) = validation.conflicts.first() {
+        return Err(Error::new(
+            ErrorCode::ConflictError,
+            format!("Barcode {} is already used by {}", barcode, owner.sku),
+        ));
+    }
+    Ok(barcode)
+}
+
+#[async_trait]
+impl BarcodeService for DefaultBarcodeService {
+    async fn validate_barcode(&self, request: ValidateBarcodeRequest) -> Result<BarcodeValidation> {
+        let analysis = analyze(&request.barcode, request.symbology, Utc::now().date_naive());
+        let owners = if analysis.valid {
+            self.repository
+                .find_barcode_owners(self.tenant_context.tenant_id, &uniqueness_key(&analysis.normalized))
+                .await?
+        } else {
+            Vec::new()
+        };
+        Ok(BarcodeValidation::new(analysis, owners, request.product_id, request.variant_id))
+    }
+
+    async fn assign_barcode(&self, product_id: Uuid, variant_id: Option<Uuid>, barcode: Option<String>) -> Result<Option<String>> {
+        let barcode = match barcode.filter(|b| !normalize(b).is_empty()) {
+            Some(barcode) => {
+                let validation = self
+                    .validate_barcode(ValidateBarcodeRequest {
+                        barcode,
+                        symbology: None,
+                        product_id: Some(product_id),
+                        variant_id,
+                    })
+                    .await?;
+                Some(require_available(&validation)?)
+            }
+            None => None,
+        };
+
+        self.repository
+            .set_barcode(self.tenant_context.tenant_id, product_id, variant_id, barcode.as_deref())
+            .await?;
+        Ok(barcode)
+    }
+
+    async fn barcode_report(&self) -> Result<BarcodeReport> {
+        let barcodes = self.repository.list_barcodes(self.tenant_context.tenant_id).await?;
+        Ok(BarcodeReport::build(barcodes, Utc::now().date_naive()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    /// Products and variants keyed by (product_id, variant_id)
+    #[derive(Default)]
+    struct MemoryRepository {
+        barcodes: Mutex<HashMap<(Uuid, Option<Uuid>), BarcodeOwner>>,
+    }
+
+    impl MemoryRepository {
+        fn add(&self, product_id: Uuid, variant_id: Option<Uuid>, sku: &str, barcode: &str) {
+            self.barcodes.lock().unwrap().insert((product_id, variant_id), BarcodeOwner {
+                product_id,
+                variant_id,
+                sku: sku.to_string(),
+                barcode: barcode.to_string(),
+            });
+        }
+
+        fn barcode(&self, product_id: Uuid, variant_id: Option<Uuid>) -> Option<String> {
+            let barcodes = self.barcodes.lock().unwrap();
+            barcodes.get(&(product_id, variant_id)).map(|o| o.barcode.clone()).filter(|b| !b.is_empty())
+        }
+    }
+
+    #[async_trait]
+    impl BarcodeRepository for MemoryRepository {
+        async fn find_barcode_owners(&self, _tenant_id: Uuid, barcode_key: &str) -> Result<Vec<BarcodeOwner>> {
+            let barcodes = self.barcodes.lock().unwrap();
+            Ok(barcodes.values().filter(|o| uniqueness_key(&o.barcode) == barcode_key).cloned().collect())
+        }
+
+        async fn set_barcode(
+            &self,
+            _tenant_id: Uuid,
+            product_id: Uuid,
+            variant_id: Option<Uuid>,
+            barcode: Option<&str>,
+        ) -> Result<()> {
+            let mut barcodes = self.barcodes.lock().unwrap();
+            // Same rule as the unique index on product_barcodes
+            if let Some(barcode) = barcode {
+                let taken = barcodes.iter().any(|(owner, o)| {
+                    *owner != (product_id, variant_id) && uniqueness_key(&o.barcode) == uniqueness_key(barcode)
+                });
+                if taken {
+                    return Err(Error::new(ErrorCode::ConflictError, "duplicate barcode"));
+                }
+            }
+            let owner = barcodes
+                .get_mut(&(product_id, variant_id))
+                .ok_or_else(|| Error::new(ErrorCode::NotFound, "not found"))?;
+            owner.barcode = barcode.unwrap_or_default().to_string();
+            Ok(())
+        }
+
+        async fn list_barcodes(&self, _tenant_id: Uuid) -> Result<Vec<BarcodeOwner>> {
+            let barcodes = self.barcodes.lock().unwrap();
+            Ok(barcodes.values().filter(|o| !o.barcode.is_empty()).cloned().collect())
+        }
+    }
+
+    fn service(repository: &Arc<MemoryRepository>) -> DefaultBarcodeService {
+        let context = TenantContext::new(Uuid::new_v4(), "acme".to_string(), Uuid::new_v4());
+        DefaultBarcodeService::new(repository.clone(), context)
+    }
+
+    fn request(barcode: &str, product_id: Option<Uuid>, variant_id: Option<Uuid>) -> ValidateBarcodeRequest {
+        ValidateBarcodeRequest {
+            barcode: barcode.to_string(),
+            symbology: None,
+            product_id,
+            variant_id,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_barcode_is_unique_across_products_and_variants() {
+        let repository = Arc::new(MemoryRepository::default());
+        let (shirt, mug) = (Uuid::new_v4(), Uuid::new_v4());
+        let shirt_red = Uuid::new_v4();
+        repository.add(shirt, None, "SHIRT", "");
+        repository.add(shirt, Some(shirt_red), "SHIRT-RED", "");
+        repository.add(mug, None, "MUG", "0036000291452");
+        let service = service(&repository);
+
+        // The UPC-A is the mug's EAN-13 without the leading zero
+        let validation = service.validate_barcode(request("036000291452", Some(shirt), Some(shirt_red))).await.unwrap();
+        assert!(validation.analysis.valid);
+        assert!(!validation.available);
+        assert_eq!(validation.conflicts[0].sku, "MUG");
+
+        let conflict = service
+            .assign_barcode(shirt, Some(shirt_red), Some("036000291452".to_string()))
+            .await
+            .unwrap_err();
+        assert_eq!(conflict.code, ErrorCode::ConflictError);
+        assert_eq!(repository.barcode(shirt, Some(shirt_red)), None);
+
+        let stored = service.assign_barcode(shirt, Some(shirt_red), Some(" 4006381333931 ".to_string())).await.unwrap();
+        assert_eq!(stored.as_deref(), Some("4006381333931"));
+
+        // A variant's barcode blocks its own parent product too
+        let parent = service.assign_barcode(shirt, None, Some("4006381333931".to_string())).await.unwrap_err();
+        assert_eq!(parent.code, ErrorCode::ConflictError);
+
+        // Re-validating a barcode for its owner is not a conflict
+        let own = service.validate_barcode(request("4006381333931", Some(shirt), Some(shirt_red))).await.unwrap();
+        assert!(own.available);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_barcode_is_rejected_and_valid_one_normalized() {
+        let repository = Arc::new(MemoryRepository::default());
+        let product = Uuid::new_v4();
+        repository.add(product, None, "A-1", "");
+        let service = service(&repository);
+
+        let invalid = service.assign_barcode(product, None, Some("4006381333932".to_string())).await.unwrap_err();
+        assert_eq!(invalid.code, ErrorCode::ValidationFailed);
+        assert_eq!(repository.barcode(product, None), None);
+
+        let stored = service.assign_barcode(product, None, Some("0 36000 29145 2".to_string())).await.unwrap();
+        assert_eq!(stored.as_deref(), Some("036000291452"));
+        assert_eq!(repository.barcode(product, None).as_deref(), Some("036000291452"));
+
+        assert_eq!(service.assign_barcode(product, None, None).await.unwrap(), None);
+        assert_eq!(repository.barcode(product, None), None);
+    }
+
+    #[tokio::test]
+    async fn test_report_flags_invalid_and_duplicate_existing_barcodes() {
+        let repository = Arc::new(MemoryRepository::default());
+        let (a, b, c) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        repository.add(a, None, "A", "036000291452");
+        repository.add(b, None, "B", "0036000291452");
+        repository.add(c, None, "C", "4006381333932");
+
+        let report = service(&repository).barcode_report().await.unwrap();
+        assert_eq!(report.checked, 3);
+        assert_eq!(report.invalid.len(), 1);
+        assert_eq!(report.invalid[0].owner.sku, "C");
+        assert_eq!(report.duplicates.len(), 1);
+        assert_eq!(report.duplicates[0].len(), 2);
+    }
+}
@@ -0,0 +1,263 @@
+//! # Bulk Price Updates
+//!
+//! Every bulk update is first computed as a [`BulkPriceDiff`]: one line per
+//! product with the current and new base price, the percentage change and any
+//! guard it trips. A dry run returns the diff and writes nothing; a real run
+//! writes all lines and the diff together as a [`BulkPriceUpdateRecord`], which
+//! is what a rollback restores from.
+//!
+//! ## Guards
+//!
+//! - the new price is below the product's cost price
+//! - the absolute change exceeds [`PriceGuardConfig::max_change_percent`]
+//!
+//! Any violation blocks a real run unless `force_guard_override` is set by a
+//! user holding [`PRICE_GUARD_OVERRIDE_PERMISSION`].
+
+use super::repository::PriceAdjustment;
+use chrono::{DateTime, Utc};
+use erp_core::error::{Error, ErrorCode, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use uuid::Uuid;
+
+/// Permission required to apply a batch despite guard violations
+pub const PRICE_GUARD_OVERRIDE_PERMISSION: &str = "products:price_guard_override";
+
+/// Limits checked for every line of a bulk price update
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PriceGuardConfig {
+    /// Largest allowed change in either direction, in percent of the current price
+    pub max_change_percent: f64,
+}
+
+impl Default for PriceGuardConfig {
+    fn default() -> Self {
+        Self {
+            max_change_percent: 25.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "guard", rename_all = "snake_case")]
+pub enum PriceGuardViolation {
+    BelowCost { cost_price: i64 },
+    ChangeAboveCap { max_change_percent: f64 },
+}
+
+/// Pricing fields of a product a bulk update needs
+#[derive(Debug, Clone)]
+pub struct ProductPriceSnapshot {
+    pub product_id: Uuid,
+    pub sku: String,
+    pub base_price: i64,
+    pub cost_price: Option<i64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PriceDiffLine {
+    pub product_id: Uuid,
+    pub sku: String,
+    pub current_price: i64,
+    pub new_price: i64,
+    /// `None` when the current price is zero
+    pub change_percent: Option<f64>,
+    pub violations: Vec<PriceGuardViolation>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PriceDiffSummary {
+    pub count: usize,
+    pub min_change_percent: Option<f64>,
+    pub max_change_percent: Option<f64>,
+    pub avg_change_percent: Option<f64>,
+    pub violation_count: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BulkPriceDiff {
+    pub lines: Vec<PriceDiffLine>,
+    pub summary: PriceDiffSummary,
+    /// Requested products that don't exist in the tenant
+    pub missing_product_ids: Vec<Uuid>,
+}
+
+impl BulkPriceDiff {
+    pub fn has_violations(&self) -> bool {
+        self.summary.violation_count > 0
+    }
+}
+
+/// What happened to a bulk update request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BulkPriceUpdateStatus {
+    /// Dry run, nothing written
+    Preview,
+    /// Guard violations without an authorized override, nothing written
+    Blocked,
+    Applied,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BulkPriceUpdateOutcome {
+    pub status: BulkPriceUpdateStatus,
+    /// Set when applied; used to look up or roll back the update
+    pub bulk_update_id: Option<Uuid>,
+    pub diff: BulkPriceDiff,
+}
+
+/// Audit record of an applied bulk update
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BulkPriceUpdateRecord {
+    pub id: Uuid,
+    pub tenant_id: Uuid,
+    pub adjustment: PriceAdjustment,
+    pub diff: BulkPriceDiff,
+    pub guard_override: bool,
+    pub performed_by: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub rolled_back_at: Option<DateTime<Utc>>,
+    pub rolled_back_by: Option<Uuid>,
+}
+
+/// New base price in cents; fixed amounts and set prices are given in cents
+pub fn adjusted_price(current: i64, adjustment: &PriceAdjustment) -> Result<i64> {
+    let new_price = match adjustment {
+        PriceAdjustment::Percentage(percent) => (current as f64 * (1.0 + percent / 100.0)).round(),
+        PriceAdjustment::FixedAmount(amount) => (current as f64 + amount).round(),
+        PriceAdjustment::SetPrice(price) => price.round(),
+    };
+
+    if !new_price.is_finite() || new_price < 0.0 {
+        return Err(Error::new(
+            ErrorCode::ValidationFailed,
+            format!("Adjustment would make a price of {} negative", current),
+        ));
+    }
+    Ok(new_price as i64)
+}
+
+fn change_percent(current: i64, new_price: i64) -> Option<f64> {
+    (current != 0).then(|| (new_price - current) as f64 / current as f64 * 100.0)
+}
+
+/// Diff for applying `adjustment` to the requested products, with guards checked per line
+pub fn compute_price_diff(
+    requested: &[Uuid],
+    snapshots: Vec<ProductPriceSnapshot>,
+    adjustment: &PriceAdjustment,
+    guards: &PriceGuardConfig,
+) -> Result<BulkPriceDiff> {
+    let mut lines = Vec::with_capacity(snapshots.len());
+    let mut seen = HashSet::new();
+
+    for snapshot in snapshots {
+        if !seen.insert(snapshot.product_id) {
+            continue;
+        }
+
+        let new_price = adjusted_price(snapshot.base_price, adjustment)?;
+        let change = change_percent(snapshot.base_price, new_price);
+
+        let mut violations = Vec::new();
+        if let Some(cost_price) = snapshot.cost_price.filter(|&cost| new_price < cost) {
+            violations.push(PriceGuardViolation::BelowCost { cost_price });
+        }
+        let above_cap = match change {
+            Some(percent) => percent.abs() > guards.max_change_percent,
+            // From zero to anything is an unbounded change
+            None => new_price != 0,
+        };
+        if above_cap {
+            violations.push(PriceGuardViolation::ChangeAboveCap {
+                max_change_percent: guards.max_change_percent,
+            });
+        }
+
+        lines.push(PriceDiffLine {
+            product_id: snapshot.product_id,
+            sku: snapshot.sku,
+            current_price: snapshot.base_price,
+            new_price,
+            change_percent: change,
+            violations,
+        });
+    }
+
+    let mut missing = Vec::new();
+    for id in requested {
+        if !seen.contains(id) && !missing.contains(id) {
+            missing.push(*id);
+        }
+    }
+
+    Ok(BulkPriceDiff {
+        summary: summarize(&lines),
+        lines,
+        missing_product_ids: missing,
+    })
+}
+
+fn summarize(lines: &[PriceDiffLine]) -> PriceDiffSummary {
+    let changes: Vec<f64> = lines.iter().filter_map(|l| l.change_percent).collect();
+
+    PriceDiffSummary {
+        count: lines.len(),
+        min_change_percent: changes.iter().copied().reduce(f64::min),
+        max_change_percent: changes.iter().copied().reduce(f64::max),
+        avg_change_percent: (!changes.is_empty()).then(|| changes.iter().sum::<f64>() / changes.len() as f64),
+        violation_count: lines.iter().filter(|l| !l.violations.is_empty()).count(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(sku: &str, base_price: i64, cost_price: Option<i64>) -> ProductPriceSnapshot {
+        ProductPriceSnapshot {
+            product_id: Uuid::new_v4(),
+            sku: sku.to_string(),
+            base_price,
+            cost_price,
+        }
+    }
+
+    #[test]
+    fn test_adjusted_price() {
+        assert_eq!(adjusted_price(1000, &PriceAdjustment::Percentage(12.5)).unwrap(), 1125);
+        assert_eq!(adjusted_price(999, &PriceAdjustment::Percentage(-10.0)).unwrap(), 899);
+        assert_eq!(adjusted_price(1000, &PriceAdjustment::FixedAmount(-250.0)).unwrap(), 750);
+        assert_eq!(adjusted_price(1000, &PriceAdjustment::SetPrice(1499.0)).unwrap(), 1499);
+        assert!(adjusted_price(100, &PriceAdjustment::FixedAmount(-101.0)).is_err());
+    }
+
+    #[test]
+    fn test_diff_flags_guard_violations_and_summarizes() {
+        let guards = PriceGuardConfig { max_change_percent: 15.0 };
+        let ok = snapshot("OK-1", 1000, Some(500));
+        let below_cost = snapshot("COST-1", 1000, Some(950));
+        let missing = Uuid::new_v4();
+        let requested = vec![ok.product_id, below_cost.product_id, missing];
+
+        let diff = compute_price_diff(&requested, vec![ok, below_cost], &PriceAdjustment::Percentage(-10.0), &guards).unwrap();
+        assert_eq!(diff.lines.len(), 2);
+        assert!(diff.lines[0].violations.is_empty());
+        assert_eq!(diff.lines[1].violations, vec![PriceGuardViolation::BelowCost { cost_price: 950 }]);
+        assert_eq!(diff.missing_product_ids, vec![missing]);
+        assert_eq!(diff.summary.count, 2);
+        assert_eq!(diff.summary.violation_count, 1);
+        assert_eq!(diff.summary.avg_change_percent, Some(-10.0));
+
+        // A wrong-category +40% trips the cap on every line
+        let lines = vec![snapshot("A", 1000, None), snapshot("B", 2000, None), snapshot("C", 0, None)];
+        let diff = compute_price_diff(&[], lines, &PriceAdjustment::Percentage(40.0), &guards).unwrap();
+        assert_eq!(diff.summary.violation_count, 2);
+        assert_eq!(diff.lines[2].change_percent, None);
+        assert!(diff.lines[2].violations.is_empty());
+        assert_eq!(diff.summary.min_change_percent, Some(40.0));
+        assert_eq!(diff.summary.max_change_percent, Some(40.0));
+    }
+}
@@ -0,0 +1,279 @@
+use async_trait::async_trait;
+use chrono::Utc;
+use erp_core::error::{Error, ErrorCode, Result};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::product::bulk_pricing::*;
+use crate::product::repository::{BulkPriceUpdateRequest, PriceUpdateRepository};
+use crate::types::TenantContext;
+
+/// Bulk price updates with dry run, guards and rollback
+#[async_trait]
+pub trait BulkPriceUpdateService: Send + Sync {
+    /// Preview, block or apply the update depending on `dry_run`, the guards and
+    /// `force_guard_override`
+    async fn bulk_update_prices(&self, request: BulkPriceUpdateRequest) -> Result<BulkPriceUpdateOutcome>;
+
+    async fn get_bulk_price_update(&self, id: Uuid) -> Result<BulkPriceUpdateRecord>;
+
+    /// Restore the prices from before the update
+    async fn rollback_bulk_price_update(&self, id: Uuid) -> Result<BulkPriceUpdateRecord>;
+}
+
+pub struct DefaultBulkPriceUpdateService {
+    repository: Arc<dyn PriceUpdateRepository>,
+    tenant_context: TenantContext,
+    guards: PriceGuardConfig,
+}
+
+impl DefaultBulkPriceUpdateService {
+    pub fn new(repository: Arc<dyn PriceUpdateRepository>, tenant_context: TenantContext, guards: PriceGuardConfig) -> Self {
+        Self {
+            repository,
+            tenant_context,
+            guards,
+        }
+    }
+}
+
+#[async_trait]
+impl BulkPriceUpdateService for DefaultBulkPriceUpdateService {
+    async fn bulk_update_prices(&self, request: BulkPriceUpdateRequest) -> Result<BulkPriceUpdateOutcome> {
+        if request.product_ids.is_empty() {
+            return Err(Error::new(ErrorCode::ValidationFailed, "No products given for the price update"));
+        }
+        if request.force_guard_override && !request.dry_run
+            && !self.tenant_context.has_permission(PRICE_GUARD_OVERRIDE_PERMISSION)
+        {
+            return Err(Error::new(
+                ErrorCode::PermissionDenied,
+                format!("Overriding price guards requires {}", PRICE_GUARD_OVERRIDE_PERMISSION),
+            ));
+        }
+
+        let tenant_id = self.tenant_context.tenant_id;
+        let snapshots = self.repository.price_snapshots(tenant_id, &request.product_ids).await?;
+        let diff = compute_price_diff(&request.product_ids, snapshots, &request.price_adjustment, &self.guards)?;
+
+        if request.dry_run {
+            return Ok(BulkPriceUpdateOutcome {
+                status: BulkPriceUpdateStatus::Preview,
+                bulk_update_id: None,
+                diff,
+            });
+        }
+        if !diff.missing_product_ids.is_empty() {
+            return Err(Error::new(
+                ErrorCode::NotFound,
+                format!("{} of the requested products were not found", diff.missing_product_ids.len()),
+            ));
+        }
+        if diff.has_violations() && !request.force_guard_override {
+            return Ok(BulkPriceUpdateOutcome {
+                status: BulkPriceUpdateStatus::Blocked,
+                bulk_update_id: None,
+                diff,
+            });
+        }
+
+        let record = BulkPriceUpdateRecord {
+            id: Uuid::new_v4(),
+            tenant_id,
+            adjustment: request.price_adjustment,
+            diff,
+            guard_override: request.force_guard_override,
+            performed_by: self.tenant_context.user_id,
+            created_at: Utc::now(),
+            rolled_back_at: None,
+            rolled_back_by: None,
+        };
+        self.repository.apply_bulk_price_update(&record).await?;
+
+        Ok(BulkPriceUpdateOutcome {
+            status: BulkPriceUpdateStatus::Applied,
+            bulk_update_id: Some(record.id),
+            diff: record.diff,
+        })
+    }
+
+    async fn get_bulk_price_update(&self, id: Uuid) -> Result<BulkPriceUpdateRecord> {
+        self.repository
+            .get_bulk_price_update(self.tenant_context.tenant_id, id)
+            .await?
+            .ok_or_else(|| Error::new(ErrorCode::NotFound, format!("Bulk price update {} not found", id)))
+    }
+
+    async fn rollback_bulk_price_update(&self, id: Uuid) -> Result<BulkPriceUpdateRecord> {
+        let mut record = self.get_bulk_price_update(id).await?;
+        if record.rolled_back_at.is_some() {
+            return Err(Error::new(ErrorCode::ConflictError, "Bulk price update was already rolled back"));
+        }
+
+        let now = Utc::now();
+        let user_id = self.tenant_context.user_id;
+        self.repository.rollback_bulk_price_update(&record, user_id, now).await?;
+
+        record.rolled_back_at = Some(now);
+        record.rolled_back_by = Some(user_id);
+        Ok(record)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::product::repository::PriceAdjustment;
+    use chrono::DateTime;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct MemoryRepository {
+        products: Mutex<HashMap<Uuid, ProductPriceSnapshot>>,
+        records: Mutex<HashMap<Uuid, BulkPriceUpdateRecord>>,
+    }
+
+    impl MemoryRepository {
+        fn add(&self, sku: &str, base_price: i64, cost_price: Option<i64>) -> Uuid {
+            let product_id = Uuid::new_v4();
+            self.products.lock().unwrap().insert(product_id, ProductPriceSnapshot {
+                product_id,
+                sku: sku.to_string(),
+                base_price,
+                cost_price,
+            });
+            product_id
+        }
+
+        fn price(&self, product_id: Uuid) -> i64 {
+            self.products.lock().unwrap()[&product_id].base_price
+        }
+    }
+
+    #[async_trait]
+    impl PriceUpdateRepository for MemoryRepository {
+        async fn price_snapshots(&self, _tenant_id: Uuid, product_ids: &[Uuid]) -> Result<Vec<ProductPriceSnapshot>> {
+            let products = self.products.lock().unwrap();
+            Ok(product_ids.iter().filter_map(|id| products.get(id).cloned()).collect())
+        }
+
+        async fn apply_bulk_price_update(&self, record: &BulkPriceUpdateRecord) -> Result<()> {
+            let mut products = self.products.lock().unwrap();
+            for line in &record.diff.lines {
+                products.get_mut(&line.product_id).unwrap().base_price = line.new_price;
+            }
+            self.records.lock().unwrap().insert(record.id, record.clone());
+            Ok(())
+        }
+
+        async fn get_bulk_price_update(&self, _tenant_id: Uuid, id: Uuid) -> Result<Option<BulkPriceUpdateRecord>> {
+            Ok(self.records.lock().unwrap().get(&id).cloned())
+        }
+
+        async fn rollback_bulk_price_update(
+            &self,
+            record: &BulkPriceUpdateRecord,
+            rolled_back_by: Uuid,
+            rolled_back_at: DateTime<Utc>,
+        ) -> Result<()> {
+            let mut products = self.products.lock().unwrap();
+            for line in &record.diff.lines {
+                products.get_mut(&line.product_id).unwrap().base_price = line.current_price;
+            }
+            let mut records = self.records.lock().unwrap();
+            let stored = records.get_mut(&record.id).unwrap();
+            stored.rolled_back_at = Some(rolled_back_at);
+            stored.rolled_back_by = Some(rolled_back_by);
+            Ok(())
+        }
+    }
+
+    fn service(repository: &Arc<MemoryRepository>, permissions: &[&str]) -> DefaultBulkPriceUpdateService {
+        let mut context = TenantContext::new(Uuid::new_v4(), "acme".to_string(), Uuid::new_v4());
+        context.permissions = permissions.iter().map(|p| p.to_string()).collect();
+        DefaultBulkPriceUpdateService::new(repository.clone(), context, PriceGuardConfig { max_change_percent: 20.0 })
+    }
+
+    fn request(product_ids: Vec<Uuid>, adjustment: PriceAdjustment, dry_run: bool, force: bool) -> BulkPriceUpdateRequest {
+        BulkPriceUpdateRequest {
+            product_ids,
+            price_adjustment: adjustment,
+            dry_run,
+            force_guard_override: force,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_guard_violation_blocks_batch_unless_overridden_with_permission() {
+        let repository = Arc::new(MemoryRepository::default());
+        let fine = repository.add("FINE", 1000, Some(600));
+        let below_cost = repository.add("THIN", 1000, Some(900));
+        let ids = vec![fine, below_cost];
+
+        let outcome = service(&repository, &[])
+            .bulk_update_prices(request(ids.clone(), PriceAdjustment::Percentage(-15.0), false, false))
+            .await
+            .unwrap();
+        assert_eq!(outcome.status, BulkPriceUpdateStatus::Blocked);
+        assert_eq!(outcome.bulk_update_id, None);
+        assert_eq!(outcome.diff.summary.violation_count, 1);
+        assert_eq!((repository.price(fine), repository.price(below_cost)), (1000, 1000));
+
+        let denied = service(&repository, &["products:write"])
+            .bulk_update_prices(request(ids.clone(), PriceAdjustment::Percentage(-15.0), false, true))
+            .await
+            .unwrap_err();
+        assert_eq!(denied.code, ErrorCode::PermissionDenied);
+        assert_eq!(repository.price(fine), 1000);
+
+        let applied = service(&repository, &[PRICE_GUARD_OVERRIDE_PERMISSION])
+            .bulk_update_prices(request(ids, PriceAdjustment::Percentage(-15.0), false, true))
+            .await
+            .unwrap();
+        assert_eq!(applied.status, BulkPriceUpdateStatus::Applied);
+        assert_eq!((repository.price(fine), repository.price(below_cost)), (850, 850));
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_returns_diff_without_writing() {
+        let repository = Arc::new(MemoryRepository::default());
+        let product = repository.add("A-1", 1999, None);
+        let missing = Uuid::new_v4();
+
+        let outcome = service(&repository, &[])
+            .bulk_update_prices(request(vec![product, missing], PriceAdjustment::FixedAmount(100.0), true, false))
+            .await
+            .unwrap();
+        assert_eq!(outcome.status, BulkPriceUpdateStatus::Preview);
+        assert_eq!(outcome.bulk_update_id, None);
+        assert_eq!(outcome.diff.lines[0].current_price, 1999);
+        assert_eq!(outcome.diff.lines[0].new_price, 2099);
+        assert_eq!(outcome.diff.missing_product_ids, vec![missing]);
+        assert_eq!(repository.price(product), 1999);
+        assert!(repository.records.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_rollback_restores_exact_previous_prices() {
+        let repository = Arc::new(MemoryRepository::default());
+        let a = repository.add("A", 1999, None);
+        let b = repository.add("B", 333, None);
+        let service = service(&repository, &[]);
+
+        let outcome = service
+            .bulk_update_prices(request(vec![a, b], PriceAdjustment::Percentage(7.5), false, false))
+            .await
+            .unwrap();
+        assert_eq!(outcome.status, BulkPriceUpdateStatus::Applied);
+        assert_eq!((repository.price(a), repository.price(b)), (2149, 358));
+
+        let id = outcome.bulk_update_id.unwrap();
+        let record = service.rollback_bulk_price_update(id).await.unwrap();
+        assert!(record.rolled_back_at.is_some());
+        assert_eq!((repository.price(a), repository.price(b)), (1999, 333));
+
+        let again = service.rollback_bulk_price_update(id).await.unwrap_err();
+        assert_eq!(again.code, ErrorCode::ConflictError);
+    }
+}
@@ -0,0 +1,102 @@
+//! # Product Enhancement
+//!
+//! The AI engine's work on a new product (category, reorder point,
+//! description, SEO texts and optimization suggestions) does not hold up its
+//! creation. Once the product is committed, the product service queues a
+//! [`ProductEnhancementJob`] (see
+//! [`DefaultProductService::with_enhancement_queue`](super::service::DefaultProductService::with_enhancement_queue));
+//! [`ProductEnhancementHandler`] runs it through
+//! [`ProductService::enhance_product`]. A failed enhancement is retried and
+//! never undoes the product.
+
+use super::service::ProductService;
+use async_trait::async_trait;
+use erp_core::error::{ErrorCode, Result};
+use erp_core::jobs::traits::JobContext;
+use erp_core::jobs::{JobHandler, JobResult, SerializableJob};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Arc;
+use uuid::Uuid;
+
+pub const PRODUCT_ENHANCEMENT_JOB: &str = "product_enhancement";
+
+/// AI enhancement of a just created product
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProductEnhancementJob {
+    pub tenant_id: Uuid,
+    pub product_id: Uuid,
+    /// The user who created the product; changes are made in their name
+    pub requested_by: Uuid,
+}
+
+impl SerializableJob for ProductEnhancementJob {
+    fn job_type(&self) -> &'static str {
+        PRODUCT_ENHANCEMENT_JOB
+    }
+
+    fn serialize(&self) -> std::result::Result<serde_json::Value, serde_json::Error> {
+        serde_json::to_value(self)
+    }
+
+    fn deserialize(data: &serde_json::Value) -> std::result::Result<Box<dyn SerializableJob>, serde_json::Error>
+    where
+        Self: Sized,
+    {
+        let job: ProductEnhancementJob = serde_json::from_value(data.clone())?;
+        Ok(Box::new(job))
+    }
+
+    fn priority(&self) -> erp_core::jobs::JobPriority {
+        erp_core::jobs::JobPriority::Low
+    }
+
+    fn metadata(&self) -> HashMap<String, serde_json::Value> {
+        HashMap::from([
+            ("tenant_id".to_string(), json!(self.tenant_id)),
+            ("product_id".to_string(), json!(self.product_id)),
+        ])
+    }
+}
+
+/// Product service acting for the tenant and user of a job
+pub type ProductServiceFactory = dyn Fn(&ProductEnhancementJob) -> Arc<dyn ProductService> + Send + Sync;
+
+/// Runs [`ProductEnhancementJob`]s
+pub struct ProductEnhancementHandler {
+    services: Arc<ProductServiceFactory>,
+}
+
+impl ProductEnhancementHandler {
+    pub fn new(services: Arc<ProductServiceFactory>) -> Self {
+        Self { services }
+    }
+}
+
+#[async_trait]
+impl JobHandler for ProductEnhancementHandler {
+    fn job_type(&self) -> &'static str {
+        PRODUCT_ENHANCEMENT_JOB
+    }
+
+    async fn handle(&self, job_data: &serde_json::Value, _context: &JobContext) -> JobResult {
+        let job: ProductEnhancementJob = match serde_json::from_value(job_data.clone()) {
+            Ok(job) => job,
+            Err(e) => return JobResult::failed(format!("invalid product enhancement job: {}", e)),
+        };
+
+        let service = (self.services)(&job);
+        match service.enhance_product(job.product_id).await {
+            Ok(product) => JobResult::success_with_result(json!({ "product_id": product.id })),
+            // Deleted before its turn came; nothing left to enhance
+            Err(e) if e.code == ErrorCode::NotFound => JobResult::cancelled(e.to_string()),
+            Err(e) => JobResult::retry(e.to_string()),
+        }
+    }
+
+    fn validate_job_data(&self, job_data: &serde_json::Value) -> Result<()> {
+        serde_json::from_value::<ProductEnhancementJob>(job_data.clone())?;
+        Ok(())
+    }
+}
@@ -0,0 +1,348 @@
+//! # Product Feed Export
+//!
+//! The tenant's catalog, optionally narrowed by an [`AdvancedProductSearch`],
+//! as one file for shops, marketplaces and spreadsheets:
+//!
+//! - `json`: an array of [`Product`]s
+//! - `csv`: one row per product; the header is the product's field names and
+//!   tags are joined with `|`; a feed without products is an empty file
+//! - `xlsx`: the CSV columns, on one sheet per product category
+//!
+//! Prices stay in cents, as everywhere else in the catalog.
+
+use std::collections::{HashMap, HashSet};
+
+use chrono::{DateTime, Utc};
+use erp_core::error::{Error, ErrorCode, Result};
+use rust_xlsxwriter::{Format, Workbook};
+use serde::Serialize;
+use uuid::Uuid;
+
+use super::model::{Product, ProductStatus, ProductType, UnitOfMeasure};
+use super::repository::{AdvancedProductSearch, ProductRepository};
+
+/// Sheet of the products without a category
+pub const UNCATEGORIZED_SHEET: &str = "Uncategorized";
+
+/// Separator of the tags in CSV and XLSX cells
+const TAG_SEPARATOR: &str = "|";
+
+/// Longest worksheet name Excel accepts
+const MAX_SHEET_NAME_LEN: usize = 31;
+
+/// File format of a product feed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProductFeedFormat {
+    Json,
+    Csv,
+    Xlsx,
+}
+
+impl ProductFeedFormat {
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "json" => Ok(Self::Json),
+            "csv" => Ok(Self::Csv),
+            "xlsx" => Ok(Self::Xlsx),
+            other => Err(Error::new(
+                ErrorCode::ValidationFailed,
+                format!("Unsupported feed format '{}'; use json, csv or xlsx", other),
+            )),
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Json => "application/json",
+            Self::Csv => "text/csv; charset=utf-8",
+            Self::Xlsx => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Csv => "csv",
+            Self::Xlsx => "xlsx",
+        }
+    }
+}
+
+/// Products of a feed with the names of their categories
+#[derive(Debug, Clone, Default)]
+pub struct ProductCatalogExport {
+    pub products: Vec<Product>,
+    pub category_names: HashMap<Uuid, String>,
+}
+
+/// A rendered product feed
+#[derive(Debug, Clone)]
+pub struct ProductFeed {
+    pub format: ProductFeedFormat,
+    pub product_count: usize,
+    pub body: Vec<u8>,
+}
+
+/// A product as one CSV or worksheet row; the fields and their order are the product's
+#[derive(Debug, Serialize)]
+struct ProductFeedRow<'a> {
+    id: Uuid,
+    tenant_id: Uuid,
+    sku: &'a str,
+    name: &'a str,
+    description: Option<&'a str>,
+    short_description: Option<&'a str>,
+    category_id: Option<Uuid>,
+    product_type: &'a ProductType,
+    status: &'a ProductStatus,
+    tags: Option<String>,
+    unit_of_measure: &'a UnitOfMeasure,
+    weight: Option<f64>,
+    dimensions_length: Option<f64>,
+    dimensions_width: Option<f64>,
+    dimensions_height: Option<f64>,
+    base_price: i64,
+    currency: &'a str,
+    cost_price: Option<i64>,
+    list_price: Option<i64>,
+    is_tracked: bool,
+    is_serialized: bool,
+    current_stock: Option<i32>,
+    min_stock_level: Option<i32>,
+    max_stock_level: Option<i32>,
+    reorder_point: Option<i32>,
+    primary_supplier_id: Option<Uuid>,
+    lead_time_days: Option<i32>,
+    barcode: Option<&'a str>,
+    brand: Option<&'a str>,
+    manufacturer: Option<&'a str>,
+    model_number: Option<&'a str>,
+    warranty_months: Option<i32>,
+    slug: Option<&'a str>,
+    meta_title: Option<&'a str>,
+    meta_description: Option<&'a str>,
+    is_featured: bool,
+    is_digital_download: bool,
+    notes: Option<&'a str>,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    created_by: Uuid,
+    updated_by: Uuid,
+}
+
+impl<'a> From<&'a Product> for ProductFeedRow<'a> {
+    fn from(product: &'a Product) -> Self {
+        Self {
+            id: product.id,
+            tenant_id: product.tenant_id,
+            sku: &product.sku,
+            name: &product.name,
+            description: product.description.as_deref(),
+            short_description: product.short_description.as_deref(),
+            category_id: product.category_id,
+            product_type: &product.product_type,
+            status: &product.status,
+            tags: product.tags.as_ref().map(|tags| tags.join(TAG_SEPARATOR)),
+            unit_of_measure: &product.unit_of_measure,
+            weight: product.weight,
+            dimensions_length: product.dimensions_length,
+            dimensions_width: product.dimensions_width,
+            dimensions_height: product.dimensions_height,
+            base_price: product.base_price,
+            currency: &product.currency,
+            cost_price: product.cost_price,
+            list_price: product.list_price,
+            is_tracked: product.is_tracked,
+            is_serialized: product.is_serialized,
+            current_stock: product.current_stock,
+            min_stock_level: product.min_stock_level,
+            max_stock_level: product.max_stock_level,
+            reorder_point: product.reorder_point,
+            primary_supplier_id: product.primary_supplier_id,
+            lead_time_days: product.lead_time_days,
+            barcode: product.barcode.as_deref(),
+            brand: product.brand.as_deref(),
+            manufacturer: product.manufacturer.as_deref(),
+            model_number: product.model_number.as_deref(),
+            warranty_months: product.warranty_months,
+            slug: product.slug.as_deref(),
+            meta_title: product.meta_title.as_deref(),
+            meta_description: product.meta_description.as_deref(),
+            is_featured: product.is_featured,
+            is_digital_download: product.is_digital_download,
+            notes: product.notes.as_deref(),
+            created_at: product.created_at,
+            updated_at: product.updated_at,
+            created_by: product.created_by,
+            updated_by: product.updated_by,
+        }
+    }
+}
+
+/// Load the tenant's products matching `search` and render them as `format`
+pub async fn export_product_feed(
+    repository: &dyn ProductRepository,
+    tenant_id: Uuid,
+    format: ProductFeedFormat,
+    search: Option<&AdvancedProductSearch>,
+) -> Result<ProductFeed> {
+    let catalog = repository.export_product_catalog(tenant_id, search).await?;
+    render_product_feed(format, &catalog)
+}
+
+pub fn render_product_feed(format: ProductFeedFormat, catalog: &ProductCatalogExport) -> Result<ProductFeed> {
+    let body = match format {
+        ProductFeedFormat::Json => serde_json::to_vec(&catalog.products).map_err(|e| feed_error("JSON", e))?,
+        ProductFeedFormat::Csv => render_csv(&catalog.products)?,
+        ProductFeedFormat::Xlsx => render_xlsx(catalog)?,
+    };
+    Ok(ProductFeed { format, product_count: catalog.products.len(), body })
+}
+
+fn render_csv(products: &[Product]) -> Result<Vec<u8>> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    for product in products {
+        writer.serialize(ProductFeedRow::from(product)).map_err(|e| feed_error("CSV", e))?;
+    }
+    writer.into_inner().map_err(|e| feed_error("CSV", e))
+}
+
+fn render_xlsx(catalog: &ProductCatalogExport) -> Result<Vec<u8>> {
+    let mut sheets: Vec<(String, Vec<&Product>)> = Vec::new();
+    let mut sheet_of_category: HashMap<Option<Uuid>, usize> = HashMap::new();
+    let mut taken = HashSet::new();
+    for product in &catalog.products {
+        let index = *sheet_of_category.entry(product.category_id).or_insert_with(|| {
+            let title = match product.category_id {
+                Some(id) => catalog.category_names.get(&id).cloned().unwrap_or_else(|| id.to_string()),
+                None => UNCATEGORIZED_SHEET.to_string(),
+            };
+            sheets.push((unique_sheet_name(&title, &mut taken), Vec::new()));
+            sheets.len() - 1
+        });
+        sheets[index].1.push(product);
+    }
+    sheets.sort_by_key(|(name, _)| name.to_lowercase());
+
+    let mut workbook = Workbook::new();
+    let header = Format::new().set_bold();
+    if sheets.is_empty() {
+        // A workbook needs a sheet; an empty feed still carries its header
+        sheets.push((UNCATEGORIZED_SHEET.to_string(), Vec::new()));
+    }
+    // Headers come from the row type; any product serves
+    let blank = Product::new(Uuid::nil(), String::new(), String::new(), Uuid::nil());
+    for (name, products) in &sheets {
+        let worksheet = workbook.add_worksheet();
+        worksheet.set_name(name.as_str()).map_err(|e| feed_error("XLSX", e))?;
+        worksheet
+            .serialize_headers_with_format(0, 0, &ProductFeedRow::from(&blank), &header)
+            .map_err(|e| feed_error("XLSX", e))?;
+        for product in products {
+            worksheet.serialize(&ProductFeedRow::from(*product)).map_err(|e| feed_error("XLSX", e))?;
+        }
+        worksheet.set_freeze_panes(1, 0).map_err(|e| feed_error("XLSX", e))?;
+        worksheet.autofit();
+    }
+    workbook.save_to_buffer().map_err(|e| feed_error("XLSX", e))
+}
+
+/// A worksheet name Excel accepts for `title` that no other sheet of the workbook has
+fn unique_sheet_name(title: &str, taken: &mut HashSet<String>) -> String {
+    let cleaned: String = title
+        .chars()
+        .map(|c| if matches!(c, '[' | ']' | ':' | '*' | '?' | '/' | '\\') { '_' } else { c })
+        .collect();
+    let cleaned = cleaned.trim().trim_matches('\'');
+    let base = if cleaned.is_empty() { UNCATEGORIZED_SHEET } else { cleaned };
+
+    let mut suffix = 1;
+    loop {
+        let tail = if suffix == 1 { String::new() } else { format!(" ({})", suffix) };
+        let keep = MAX_SHEET_NAME_LEN - tail.chars().count();
+        let name = format!("{}{}", base.chars().take(keep).collect::<String>(), tail);
+        if taken.insert(name.to_lowercase()) {
+            return name;
+        }
+        suffix += 1;
+    }
+}
+
+fn feed_error(format: &str, e: impl std::fmt::Display) -> Error {
+    Error::new(ErrorCode::InternalServerError, format!("Failed to write {} product feed: {}", format, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn product(sku: &str, category_id: Option<Uuid>) -> Product {
+        let mut product = Product::new(Uuid::new_v4(), sku.to_string(), format!("Product {}", sku), Uuid::new_v4());
+        product.category_id = category_id;
+        product.base_price = 1250;
+        product.tags = Some(vec!["red".to_string(), "sale".to_string()]);
+        product
+    }
+
+    #[test]
+    fn test_format_names_are_case_insensitive() {
+        assert_eq!(ProductFeedFormat::parse("XLSX").unwrap(), ProductFeedFormat::Xlsx);
+        assert_eq!(ProductFeedFormat::parse("csv").unwrap(), ProductFeedFormat::Csv);
+        assert_eq!(ProductFeedFormat::parse("xml").unwrap_err().code, ErrorCode::ValidationFailed);
+    }
+
+    #[test]
+    fn test_csv_header_is_the_product_field_names() {
+        let catalog = ProductCatalogExport { products: vec![product("A-1", None)], ..Default::default() };
+        let feed = render_product_feed(ProductFeedFormat::Csv, &catalog).unwrap();
+        let text = String::from_utf8(feed.body).unwrap();
+        let mut lines = text.lines();
+
+        let header = lines.next().unwrap();
+        assert!(header.starts_with("id,tenant_id,sku,name,description,"));
+        assert!(header.ends_with(",created_by,updated_by"));
+        let row = lines.next().unwrap();
+        assert!(row.contains(",A-1,Product A-1,"));
+        assert!(row.contains(",red|sale,"));
+        assert_eq!(lines.next(), None);
+    }
+
+    #[test]
+    fn test_json_feed_is_the_product_array() {
+        let catalog = ProductCatalogExport { products: vec![product("A-1", None), product("A-2", None)], ..Default::default() };
+        let feed = render_product_feed(ProductFeedFormat::Json, &catalog).unwrap();
+        let products: Vec<Product> = serde_json::from_slice(&feed.body).unwrap();
+        assert_eq!(products.len(), 2);
+        assert_eq!(feed.product_count, 2);
+    }
+
+    #[test]
+    fn test_xlsx_feed_is_a_workbook() {
+        let tools = Uuid::new_v4();
+        let catalog = ProductCatalogExport {
+            products: vec![product("A-1", Some(tools)), product("A-2", None), product("A-3", Some(tools))],
+            category_names: HashMap::from([(tools, "Tools".to_string())]),
+        };
+        let feed = render_product_feed(ProductFeedFormat::Xlsx, &catalog).unwrap();
+        // XLSX files are zip archives
+        assert_eq!(&feed.body[..2], b"PK");
+
+        let empty = render_product_feed(ProductFeedFormat::Xlsx, &ProductCatalogExport::default()).unwrap();
+        assert_eq!(&empty.body[..2], b"PK");
+    }
+
+    #[test]
+    fn test_sheet_names_are_valid_and_unique() {
+        let mut taken = HashSet::new();
+        assert_eq!(unique_sheet_name("Tools / Hardware", &mut taken), "Tools _ Hardware");
+        assert_eq!(unique_sheet_name("tools _ hardware", &mut taken), "tools _ hardware (2)");
+        assert_eq!(unique_sheet_name("   ", &mut taken), UNCATEGORIZED_SHEET);
+
+        let long = "A category name well beyond the worksheet limit";
+        let first = unique_sheet_name(long, &mut taken);
+        let second = unique_sheet_name(long, &mut taken);
+        assert_eq!(first.chars().count(), MAX_SHEET_NAME_LEN);
+        assert_eq!(second.chars().count(), MAX_SHEET_NAME_LEN);
+        assert!(second.ends_with(" (2)"));
+    }
+}
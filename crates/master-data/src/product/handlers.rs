@@ -20,6 +20,7 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
+use erp_core::ErrorCode;
 
 pub type ProductServiceRef = Arc<dyn ProductService + Send + Sync>;
 pub type AnalyticsEngineRef = Arc<dyn ProductAnalyticsEngine + Send + Sync>;
@@ -336,9 +337,13 @@ impl ProductHandlers {
         State((service, _)): State<(ProductServiceRef, AnalyticsEngineRef)>,
         Json(request): Json<CreateProductRequest>,
     ) -> Result<Json<ProductResponse>, StatusCode> {
+        // A plan limit refuses with its own status; everything else stays a 500
         let product = service.create_product(request)
             .await
-            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+            .map_err(|e| match e.code {
+                ErrorCode::PlanLimitExceeded => StatusCode::FORBIDDEN,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            })?;
 
         Ok(Json(ProductResponse {
             product,
@@ -0,0 +1,96 @@
+//! # Product Integrity
+//!
+//! A product is created in one transaction together with its attributes, its
+//! first analytics record and its lifecycle (see
+//! [`ProductRepository::create_product_graph`](super::repository::ProductRepository::create_product_graph)),
+//! and deleted together with them. Products written before that could be
+//! left half-done:
+//!
+//! - attribute, analytics and lifecycle rows whose product no longer exists
+//! - products without a lifecycle record
+//!
+//! The check lists both per tenant. A repair deletes the orphaned rows and
+//! gives each product without a lifecycle the stage the lifecycle automation
+//! already assumes for it (see [`initial_stage`]), recorded as a transition of
+//! rule [`RULE_INTEGRITY_REPAIR`] so the automation does not treat it as set
+//! by hand.
+
+use super::model::{LifecycleStage, ProductStatus};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Rule of the lifecycle transitions a repair records
+pub const RULE_INTEGRITY_REPAIR: &str = "integrity_repair";
+
+/// Rule of the first lifecycle transition of a new product. Its stage is the
+/// default, not someone's decision, so it does not freeze the automation.
+pub const RULE_PRODUCT_CREATED: &str = "product_created";
+
+/// Rows of a tenant's products that are out of step
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProductIntegrityReport {
+    /// Products that no longer exist but still have attribute rows
+    pub orphaned_attributes: Vec<Uuid>,
+    /// Products that no longer exist but still have analytics rows
+    pub orphaned_analytics: Vec<Uuid>,
+    /// Products that no longer exist but still have a lifecycle record
+    pub orphaned_lifecycles: Vec<Uuid>,
+    /// Products without a lifecycle record
+    pub missing_lifecycles: Vec<Uuid>,
+}
+
+impl ProductIntegrityReport {
+    pub fn is_consistent(&self) -> bool {
+        self.orphaned_attributes.is_empty()
+            && self.orphaned_analytics.is_empty()
+            && self.orphaned_lifecycles.is_empty()
+            && self.missing_lifecycles.is_empty()
+    }
+}
+
+/// What a repair changed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProductIntegrityRepair {
+    pub removed_attributes: u64,
+    pub removed_analytics: u64,
+    pub removed_lifecycles: u64,
+    /// Products given a lifecycle record, with the stage they got
+    pub created_lifecycles: Vec<(Uuid, LifecycleStage)>,
+    pub repaired_by: Uuid,
+    pub repaired_at: DateTime<Utc>,
+}
+
+/// Stage a product's lifecycle record starts at, on creation or repair:
+/// active products are on the market since they were created, as the
+/// automation assumes; discontinued ones are at their end of life; the
+/// others are still in development
+pub fn initial_stage(status: &ProductStatus) -> LifecycleStage {
+    match status {
+        ProductStatus::Active => LifecycleStage::Launch,
+        ProductStatus::Discontinued => LifecycleStage::EndOfLife,
+        ProductStatus::Inactive | ProductStatus::Development | ProductStatus::Planned => LifecycleStage::Development,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_initial_stage_follows_the_product_status() {
+        assert_eq!(initial_stage(&ProductStatus::Active), LifecycleStage::Launch);
+        assert_eq!(initial_stage(&ProductStatus::Discontinued), LifecycleStage::EndOfLife);
+        assert_eq!(initial_stage(&ProductStatus::Planned), LifecycleStage::Development);
+    }
+
+    #[test]
+    fn test_any_finding_makes_the_report_inconsistent() {
+        assert!(ProductIntegrityReport::default().is_consistent());
+        let report = ProductIntegrityReport {
+            missing_lifecycles: vec![Uuid::new_v4()],
+            ..Default::default()
+        };
+        assert!(!report.is_consistent());
+    }
+}
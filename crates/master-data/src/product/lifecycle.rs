@@ -0,0 +1,478 @@
+//! # Product Lifecycle Automation
+//!
+//! Moves products along Launch → Growth → Maturity → Decline from their age
+//! and sales instead of waiting for someone to update the stage. Units sold
+//! and revenue are compared over two trailing windows of
+//! [`LifecycleRules::window_days`]: the most recent one and the one before.
+//! The rules, checked in order for the product's current stage:
+//!
+//! - **Launch → Growth** when units grew by `growth_trend_percent`, or the
+//!   product started selling at least `min_units` after selling nothing
+//! - **Launch → Maturity** once the product is `introduction_max_days` old
+//! - **Growth → Maturity** when unit growth fell below `growth_trend_percent`,
+//!   or once the product is `maturity_after_days` old
+//! - **Maturity → Decline** when units dropped by `decline_trend_percent`, or
+//!   the product's share of the tenant's revenue dropped by
+//!   `contribution_decline_percent`
+//!
+//! Trends only count when the earlier window sold at least `min_units`. The
+//! other stages are only ever changed by hand. A stage someone set by hand
+//! is frozen for `manual_freeze_days` so the automation does not fight it.
+
+use super::model::{LifecycleStage, ProductLifecycle};
+use super::service::LifecycleRecommendation;
+use super::suggestion::{ProductSuggestion, SuggestionSource, SuggestionStatus, LIFECYCLE_STAGE};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+pub const RULE_SALES_GROWTH: &str = "sales_growth";
+pub const RULE_INTRODUCTION_AGE: &str = "introduction_age";
+pub const RULE_SALES_PLATEAU: &str = "sales_plateau";
+pub const RULE_MATURITY_AGE: &str = "maturity_age";
+pub const RULE_SALES_DECLINE: &str = "sales_decline";
+pub const RULE_CONTRIBUTION_DECLINE: &str = "revenue_contribution_decline";
+
+/// What the automation does with the transitions it finds, per tenant
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LifecycleAutomationMode {
+    Off,
+    /// Propose transitions in the product suggestion review
+    Suggest,
+    /// Apply transitions right away, recording their evidence
+    Auto,
+}
+
+impl LifecycleAutomationMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LifecycleAutomationMode::Off => "off",
+            LifecycleAutomationMode::Suggest => "suggest",
+            LifecycleAutomationMode::Auto => "auto",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "off" => Some(LifecycleAutomationMode::Off),
+            "suggest" => Some(LifecycleAutomationMode::Suggest),
+            "auto" => Some(LifecycleAutomationMode::Auto),
+            _ => None,
+        }
+    }
+}
+
+/// Who changed a product's stage
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StageChangeSource {
+    Manual,
+    /// Applied by the automation in auto mode
+    Automation,
+    /// A reviewer accepted the automation's suggestion
+    Suggestion,
+}
+
+impl StageChangeSource {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            StageChangeSource::Manual => "manual",
+            StageChangeSource::Automation => "automation",
+            StageChangeSource::Suggestion => "suggestion",
+        }
+    }
+
+    /// Stages set by hand freeze the automation
+    pub fn is_manual(self) -> bool {
+        self == StageChangeSource::Manual
+    }
+}
+
+/// A stage change to record on the product's lifecycle
+#[derive(Debug, Clone)]
+pub struct StageChange {
+    pub product_id: Uuid,
+    pub to: LifecycleStage,
+    pub source: StageChangeSource,
+    pub rule: Option<String>,
+    pub evidence: Option<Value>,
+    pub changed_by: Uuid,
+    pub changed_at: DateTime<Utc>,
+}
+
+/// An active product the automation evaluates
+#[derive(Debug, Clone)]
+pub struct LifecycleCandidate {
+    pub product_id: Uuid,
+    pub product_created_at: DateTime<Utc>,
+    pub product_updated_at: DateTime<Utc>,
+    pub lifecycle: Option<ProductLifecycle>,
+}
+
+impl LifecycleCandidate {
+    /// Active products without a lifecycle record are on the market since
+    /// they were created
+    pub fn stage(&self) -> LifecycleStage {
+        self.lifecycle.as_ref().map_or(LifecycleStage::Launch, |lifecycle| lifecycle.stage)
+    }
+
+    pub fn launched_at(&self) -> DateTime<Utc> {
+        self.lifecycle
+            .as_ref()
+            .and_then(|lifecycle| lifecycle.launched_at)
+            .unwrap_or(self.product_created_at)
+    }
+}
+
+/// Units sold and revenue of one product in the two windows
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ProductSales {
+    pub units_recent: i64,
+    pub units_prior: i64,
+    pub revenue_recent: i64,
+    pub revenue_prior: i64,
+}
+
+/// A tenant's sales in the two windows ending at the evaluation
+#[derive(Debug, Clone, Default)]
+pub struct LifecycleSales {
+    pub products: HashMap<Uuid, ProductSales>,
+    pub tenant_revenue_recent: i64,
+    pub tenant_revenue_prior: i64,
+}
+
+impl LifecycleSales {
+    pub fn metrics(&self, candidate: &LifecycleCandidate, now: DateTime<Utc>) -> LifecycleMetrics {
+        let sales = self.products.get(&candidate.product_id).copied().unwrap_or_default();
+        LifecycleMetrics {
+            days_since_launch: (now - candidate.launched_at()).num_days(),
+            units_recent: sales.units_recent,
+            units_prior: sales.units_prior,
+            revenue_recent: sales.revenue_recent,
+            revenue_prior: sales.revenue_prior,
+            tenant_revenue_recent: self.tenant_revenue_recent,
+            tenant_revenue_prior: self.tenant_revenue_prior,
+        }
+    }
+}
+
+/// Thresholds of the lifecycle rules
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LifecycleRules {
+    /// Tenants that did not choose a mode get this one
+    pub default_mode: LifecycleAutomationMode,
+    pub window_days: i64,
+    pub introduction_max_days: i64,
+    pub maturity_after_days: i64,
+    pub growth_trend_percent: f64,
+    pub decline_trend_percent: f64,
+    pub contribution_decline_percent: f64,
+    pub min_units: i64,
+    pub manual_freeze_days: i64,
+}
+
+impl Default for LifecycleRules {
+    fn default() -> Self {
+        Self {
+            default_mode: LifecycleAutomationMode::Suggest,
+            window_days: 90,
+            introduction_max_days: 180,
+            maturity_after_days: 730,
+            growth_trend_percent: 20.0,
+            decline_trend_percent: 20.0,
+            contribution_decline_percent: 30.0,
+            min_units: 10,
+            manual_freeze_days: 90,
+        }
+    }
+}
+
+/// Sales of a product and its tenant in the recent and the prior window;
+/// revenue in cents
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LifecycleMetrics {
+    pub days_since_launch: i64,
+    pub units_recent: i64,
+    pub units_prior: i64,
+    pub revenue_recent: i64,
+    pub revenue_prior: i64,
+    pub tenant_revenue_recent: i64,
+    pub tenant_revenue_prior: i64,
+}
+
+impl LifecycleMetrics {
+    /// Change of units sold from the prior to the recent window, in percent
+    pub fn units_trend_percent(&self) -> Option<f64> {
+        (self.units_prior > 0)
+            .then(|| (self.units_recent - self.units_prior) as f64 / self.units_prior as f64 * 100.0)
+    }
+
+    /// The product's share of the tenant's revenue in a window, in percent
+    fn share(revenue: i64, tenant_revenue: i64) -> Option<f64> {
+        (tenant_revenue > 0).then(|| revenue as f64 / tenant_revenue as f64 * 100.0)
+    }
+
+    pub fn contribution_recent_percent(&self) -> Option<f64> {
+        Self::share(self.revenue_recent, self.tenant_revenue_recent)
+    }
+
+    pub fn contribution_prior_percent(&self) -> Option<f64> {
+        Self::share(self.revenue_prior, self.tenant_revenue_prior)
+    }
+
+    /// Relative change of the revenue share between the windows, in percent
+    pub fn contribution_change_percent(&self) -> Option<f64> {
+        let prior = self.contribution_prior_percent().filter(|share| *share > 0.0)?;
+        let recent = self.contribution_recent_percent()?;
+        Some((recent - prior) / prior * 100.0)
+    }
+}
+
+/// The metric values a transition was made on
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LifecycleEvidence {
+    pub rule: String,
+    pub window_days: i64,
+    #[serde(flatten)]
+    pub metrics: LifecycleMetrics,
+    pub units_trend_percent: Option<f64>,
+    pub contribution_recent_percent: Option<f64>,
+    pub contribution_prior_percent: Option<f64>,
+    pub contribution_change_percent: Option<f64>,
+}
+
+impl LifecycleEvidence {
+    pub fn new(rule: &str, metrics: &LifecycleMetrics, rules: &LifecycleRules) -> Self {
+        Self {
+            rule: rule.to_string(),
+            window_days: rules.window_days,
+            metrics: metrics.clone(),
+            units_trend_percent: metrics.units_trend_percent(),
+            contribution_recent_percent: metrics.contribution_recent_percent(),
+            contribution_prior_percent: metrics.contribution_prior_percent(),
+            contribution_change_percent: metrics.contribution_change_percent(),
+        }
+    }
+
+    pub fn to_value(&self) -> Value {
+        serde_json::to_value(self).unwrap_or(Value::Null)
+    }
+}
+
+/// A stage change the rules call for
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LifecycleTransition {
+    pub from: LifecycleStage,
+    pub to: LifecycleStage,
+    pub rule: String,
+    pub evidence: LifecycleEvidence,
+}
+
+impl LifecycleTransition {
+    fn new(from: LifecycleStage, to: LifecycleStage, rule: &str, metrics: &LifecycleMetrics, rules: &LifecycleRules) -> Self {
+        Self {
+            from,
+            to,
+            rule: rule.to_string(),
+            evidence: LifecycleEvidence::new(rule, metrics, rules),
+        }
+    }
+
+    pub fn description(&self) -> String {
+        let evidence = &self.evidence;
+        let reason = match self.rule.as_str() {
+            RULE_SALES_GROWTH | RULE_SALES_PLATEAU | RULE_SALES_DECLINE => format!(
+                "units sold went from {} to {} over the last two {}-day windows",
+                evidence.metrics.units_prior, evidence.metrics.units_recent, evidence.window_days
+            ),
+            RULE_CONTRIBUTION_DECLINE => format!(
+                "its share of revenue went from {:.1}% to {:.1}%",
+                evidence.contribution_prior_percent.unwrap_or_default(),
+                evidence.contribution_recent_percent.unwrap_or_default()
+            ),
+            _ => format!("it was launched {} days ago", evidence.metrics.days_since_launch),
+        };
+        format!("Move from {} to {}: {}", self.from.as_str(), self.to.as_str(), reason)
+    }
+}
+
+/// The transition the rules call for, if any
+pub fn evaluate(stage: LifecycleStage, metrics: &LifecycleMetrics, rules: &LifecycleRules) -> Option<LifecycleTransition> {
+    let trend = metrics.units_trend_percent().filter(|_| metrics.units_prior >= rules.min_units);
+    let transition = |to, rule| Some(LifecycleTransition::new(stage, to, rule, metrics, rules));
+
+    match stage {
+        LifecycleStage::Launch => {
+            let took_off = metrics.units_prior == 0 && metrics.units_recent >= rules.min_units;
+            if took_off || trend.is_some_and(|trend| trend >= rules.growth_trend_percent) {
+                transition(LifecycleStage::Growth, RULE_SALES_GROWTH)
+            } else if metrics.days_since_launch >= rules.introduction_max_days {
+                transition(LifecycleStage::Maturity, RULE_INTRODUCTION_AGE)
+            } else {
+                None
+            }
+        }
+        LifecycleStage::Growth => {
+            if trend.is_some_and(|trend| trend < rules.growth_trend_percent) {
+                transition(LifecycleStage::Maturity, RULE_SALES_PLATEAU)
+            } else if metrics.days_since_launch >= rules.maturity_after_days {
+                transition(LifecycleStage::Maturity, RULE_MATURITY_AGE)
+            } else {
+                None
+            }
+        }
+        LifecycleStage::Maturity => {
+            if trend.is_some_and(|trend| trend <= -rules.decline_trend_percent) {
+                transition(LifecycleStage::Decline, RULE_SALES_DECLINE)
+            } else if metrics
+                .contribution_change_percent()
+                .is_some_and(|change| change <= -rules.contribution_decline_percent)
+            {
+                transition(LifecycleStage::Decline, RULE_CONTRIBUTION_DECLINE)
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Whether someone set the stage by hand within the freeze window
+pub fn is_frozen(lifecycle: &ProductLifecycle, now: DateTime<Utc>, freeze_days: i64) -> bool {
+    !lifecycle.stage_set_by_automation && now - lifecycle.stage_changed_at < Duration::days(freeze_days)
+}
+
+/// The transition as a lifecycle recommendation
+pub fn recommendation(transition: &LifecycleTransition) -> LifecycleRecommendation {
+    LifecycleRecommendation {
+        recommendation_type: format!("move_to_{}", transition.to.as_str()),
+        description: transition.description(),
+        urgency: match transition.to {
+            LifecycleStage::Decline => "high",
+            _ => "medium",
+        }
+        .to_string(),
+        expected_impact: transition.rule.clone(),
+        implementation_cost: None,
+    }
+}
+
+/// A pending review entry proposing the transition. `current_value` is the
+/// stage the product had, so accepting it after the stage changed expires it.
+pub fn suggestion(
+    tenant_id: Uuid,
+    product_id: Uuid,
+    product_updated_at: DateTime<Utc>,
+    transition: &LifecycleTransition,
+    now: DateTime<Utc>,
+) -> ProductSuggestion {
+    ProductSuggestion {
+        id: Uuid::new_v4(),
+        tenant_id,
+        product_id,
+        suggestion_type: LIFECYCLE_STAGE.to_string(),
+        rationale: transition.description(),
+        expected_impact: None,
+        current_value: Value::from(transition.from.as_str()),
+        suggested_value: json!({
+            "stage": transition.to.as_str(),
+            "rule": transition.rule,
+            "evidence": transition.evidence.to_value(),
+        }),
+        confidence: 1.0,
+        status: SuggestionStatus::Pending,
+        source: SuggestionSource::Job,
+        product_updated_at,
+        created_at: now,
+        decided_by: None,
+        decided_at: None,
+        rejection_reason: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metrics(days_since_launch: i64, units_prior: i64, units_recent: i64) -> LifecycleMetrics {
+        LifecycleMetrics {
+            days_since_launch,
+            units_prior,
+            units_recent,
+            revenue_prior: units_prior * 1000,
+            revenue_recent: units_recent * 1000,
+            tenant_revenue_prior: 1_000_000,
+            tenant_revenue_recent: 1_000_000,
+        }
+    }
+
+    fn next(stage: LifecycleStage, metrics: &LifecycleMetrics) -> Option<(LifecycleStage, String)> {
+        evaluate(stage, metrics, &LifecycleRules::default()).map(|t| (t.to, t.rule))
+    }
+
+    #[test]
+    fn test_launch_rules() {
+        let growth = Some((LifecycleStage::Growth, RULE_SALES_GROWTH.to_string()));
+        assert_eq!(next(LifecycleStage::Launch, &metrics(30, 20, 30)), growth);
+        assert_eq!(next(LifecycleStage::Launch, &metrics(30, 0, 12)), growth, "started selling");
+        assert_eq!(next(LifecycleStage::Launch, &metrics(30, 4, 9)), None, "too few units for a trend");
+        assert_eq!(next(LifecycleStage::Launch, &metrics(30, 20, 22)), None);
+
+        // Still "new" years later without taking off
+        assert_eq!(
+            next(LifecycleStage::Launch, &metrics(900, 20, 22)),
+            Some((LifecycleStage::Maturity, RULE_INTRODUCTION_AGE.to_string()))
+        );
+    }
+
+    #[test]
+    fn test_growth_rules() {
+        assert_eq!(next(LifecycleStage::Growth, &metrics(200, 100, 150)), None);
+        assert_eq!(
+            next(LifecycleStage::Growth, &metrics(200, 100, 105)),
+            Some((LifecycleStage::Maturity, RULE_SALES_PLATEAU.to_string()))
+        );
+        assert_eq!(
+            next(LifecycleStage::Growth, &metrics(800, 2, 6)),
+            Some((LifecycleStage::Maturity, RULE_MATURITY_AGE.to_string()))
+        );
+    }
+
+    #[test]
+    fn test_maturity_rules() {
+        assert_eq!(next(LifecycleStage::Maturity, &metrics(900, 100, 90)), None);
+        assert_eq!(
+            next(LifecycleStage::Maturity, &metrics(900, 100, 75)),
+            Some((LifecycleStage::Decline, RULE_SALES_DECLINE.to_string()))
+        );
+
+        // Same units, but the rest of the catalog grew: the share halved
+        let mut shrinking = metrics(900, 100, 100);
+        shrinking.tenant_revenue_recent = 2_000_000;
+        let transition = evaluate(LifecycleStage::Maturity, &shrinking, &LifecycleRules::default()).unwrap();
+        assert_eq!((transition.to, transition.rule.as_str()), (LifecycleStage::Decline, RULE_CONTRIBUTION_DECLINE));
+        assert_eq!(transition.evidence.contribution_change_percent, Some(-50.0));
+        assert_eq!(transition.evidence.contribution_prior_percent, Some(10.0));
+    }
+
+    #[test]
+    fn test_other_stages_are_manual() {
+        for stage in [LifecycleStage::Development, LifecycleStage::Decline, LifecycleStage::EndOfLife] {
+            assert_eq!(next(stage, &metrics(2000, 100, 0)), None);
+        }
+    }
+
+    #[test]
+    fn test_evidence_records_the_metrics() {
+        let transition = evaluate(LifecycleStage::Launch, &metrics(30, 20, 30), &LifecycleRules::default()).unwrap();
+        let evidence = transition.evidence.to_value();
+        assert_eq!(evidence["rule"], RULE_SALES_GROWTH);
+        assert_eq!(evidence["units_prior"], 20);
+        assert_eq!(evidence["units_recent"], 30);
+        assert_eq!(evidence["units_trend_percent"], 50.0);
+        assert_eq!(evidence["window_days"], 90);
+    }
+}
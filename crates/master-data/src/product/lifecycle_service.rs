@@ -0,0 +1,445 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use erp_core::error::{Error, ErrorCode, Result};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::product::lifecycle::*;
+use crate::product::model::{LifecycleStage, ProductLifecycle};
+use crate::product::repository::ProductLifecycleRepository;
+use crate::types::TenantContext;
+
+/// Where a product stands against the lifecycle rules
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LifecycleEvaluation {
+    pub product_id: Uuid,
+    pub stage: LifecycleStage,
+    pub lifecycle: Option<ProductLifecycle>,
+    pub metrics: LifecycleMetrics,
+    /// The transition the rules call for
+    pub transition: Option<LifecycleTransition>,
+    /// Set by hand within the freeze window: the automation leaves it alone
+    pub frozen: bool,
+}
+
+/// What one run over a tenant's products did
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LifecycleRun {
+    pub mode: Option<LifecycleAutomationMode>,
+    pub evaluated: usize,
+    /// Transitions proposed in the suggestion review
+    pub suggested: usize,
+    /// Transitions applied in auto mode
+    pub applied: usize,
+    /// Products skipped because their stage was set by hand recently
+    pub frozen: usize,
+}
+
+/// Lifecycle stage automation of one tenant's products
+#[async_trait]
+pub trait LifecycleAutomationService: Send + Sync {
+    /// Evaluate the active products and, depending on the tenant's mode,
+    /// propose or apply the transitions the rules call for
+    async fn run(&self, now: DateTime<Utc>) -> Result<LifecycleRun>;
+
+    async fn evaluate_product(&self, product_id: Uuid, now: DateTime<Utc>) -> Result<LifecycleEvaluation>;
+
+    async fn automation_mode(&self) -> Result<LifecycleAutomationMode>;
+
+    async fn set_automation_mode(&self, mode: LifecycleAutomationMode) -> Result<LifecycleAutomationMode>;
+
+    /// Set the stage by hand. This starts the freeze window, also when the
+    /// product already has the stage, which pins it against the automation.
+    async fn set_stage(&self, product_id: Uuid, stage: LifecycleStage) -> Result<ProductLifecycle>;
+}
+
+pub struct DefaultLifecycleAutomationService {
+    repository: Arc<dyn ProductLifecycleRepository>,
+    tenant_context: TenantContext,
+    rules: LifecycleRules,
+}
+
+impl DefaultLifecycleAutomationService {
+    pub fn new(repository: Arc<dyn ProductLifecycleRepository>, tenant_context: TenantContext, rules: LifecycleRules) -> Self {
+        Self {
+            repository,
+            tenant_context,
+            rules,
+        }
+    }
+
+    async fn candidate(&self, product_id: Uuid) -> Result<LifecycleCandidate> {
+        self.repository
+            .lifecycle_candidates(self.tenant_context.tenant_id, Some(product_id))
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| Error::new(ErrorCode::NotFound, "Product not found"))
+    }
+
+    fn is_frozen(&self, candidate: &LifecycleCandidate, now: DateTime<Utc>) -> bool {
+        candidate
+            .lifecycle
+            .as_ref()
+            .is_some_and(|lifecycle| is_frozen(lifecycle, now, self.rules.manual_freeze_days))
+    }
+}
+
+#[async_trait]
+impl LifecycleAutomationService for DefaultLifecycleAutomationService {
+    async fn run(&self, now: DateTime<Utc>) -> Result<LifecycleRun> {
+        let mode = self.automation_mode().await?;
+        let mut run = LifecycleRun {
+            mode: Some(mode),
+            ..Default::default()
+        };
+        if mode == LifecycleAutomationMode::Off {
+            return Ok(run);
+        }
+
+        let tenant_id = self.tenant_context.tenant_id;
+        let candidates = self.repository.lifecycle_candidates(tenant_id, None).await?;
+        let sales = self.repository.lifecycle_sales(tenant_id, now, self.rules.window_days).await?;
+
+        for candidate in candidates {
+            run.evaluated += 1;
+            if self.is_frozen(&candidate, now) {
+                run.frozen += 1;
+                continue;
+            }
+            let Some(transition) = evaluate(candidate.stage(), &sales.metrics(&candidate, now), &self.rules) else {
+                continue;
+            };
+
+            match mode {
+                LifecycleAutomationMode::Suggest => {
+                    let suggestion = suggestion(tenant_id, candidate.product_id, candidate.product_updated_at, &transition, now);
+                    if self.repository.propose_stage_change(&suggestion).await? {
+                        run.suggested += 1;
+                    }
+                }
+                LifecycleAutomationMode::Auto => {
+                    self.repository
+                        .record_stage_change(
+                            tenant_id,
+                            &StageChange {
+                                product_id: candidate.product_id,
+                                to: transition.to,
+                                source: StageChangeSource::Automation,
+                                rule: Some(transition.rule.clone()),
+                                evidence: Some(transition.evidence.to_value()),
+                                changed_by: self.tenant_context.user_id,
+                                changed_at: now,
+                            },
+                        )
+                        .await?;
+                    tracing::info!(
+                        "Lifecycle automation moved product {} of tenant {}: {}",
+                        candidate.product_id,
+                        tenant_id,
+                        transition.description()
+                    );
+                    run.applied += 1;
+                }
+                LifecycleAutomationMode::Off => {}
+            }
+        }
+        Ok(run)
+    }
+
+    async fn evaluate_product(&self, product_id: Uuid, now: DateTime<Utc>) -> Result<LifecycleEvaluation> {
+        let candidate = self.candidate(product_id).await?;
+        let sales = self
+            .repository
+            .lifecycle_sales(self.tenant_context.tenant_id, now, self.rules.window_days)
+            .await?;
+        let metrics = sales.metrics(&candidate, now);
+
+        Ok(LifecycleEvaluation {
+            product_id,
+            stage: candidate.stage(),
+            transition: evaluate(candidate.stage(), &metrics, &self.rules),
+            frozen: self.is_frozen(&candidate, now),
+            lifecycle: candidate.lifecycle,
+            metrics,
+        })
+    }
+
+    async fn automation_mode(&self) -> Result<LifecycleAutomationMode> {
+        Ok(self
+            .repository
+            .automation_mode(self.tenant_context.tenant_id)
+            .await?
+            .unwrap_or(self.rules.default_mode))
+    }
+
+    async fn set_automation_mode(&self, mode: LifecycleAutomationMode) -> Result<LifecycleAutomationMode> {
+        self.repository
+            .set_automation_mode(self.tenant_context.tenant_id, mode, self.tenant_context.user_id)
+            .await?;
+        Ok(mode)
+    }
+
+    async fn set_stage(&self, product_id: Uuid, stage: LifecycleStage) -> Result<ProductLifecycle> {
+        self.candidate(product_id).await?;
+        self.repository
+            .record_stage_change(
+                self.tenant_context.tenant_id,
+                &StageChange {
+                    product_id,
+                    to: stage,
+                    source: StageChangeSource::Manual,
+                    rule: None,
+                    evidence: None,
+                    changed_by: self.tenant_context.user_id,
+                    changed_at: Utc::now(),
+                },
+            )
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::product::suggestion::{ProductSuggestion, SuggestionStatus, LIFECYCLE_STAGE};
+    use chrono::Duration;
+    use std::sync::Mutex;
+
+    /// Lifecycles, sales and suggestions in memory
+    #[derive(Default)]
+    struct MemoryLifecycles {
+        mode: Mutex<Option<LifecycleAutomationMode>>,
+        candidates: Mutex<Vec<LifecycleCandidate>>,
+        sales: LifecycleSales,
+        suggestions: Mutex<Vec<ProductSuggestion>>,
+        changes: Mutex<Vec<StageChange>>,
+    }
+
+    impl MemoryLifecycles {
+        fn stage(&self, product_id: Uuid) -> LifecycleStage {
+            self.candidates.lock().unwrap().iter().find(|c| c.product_id == product_id).unwrap().stage()
+        }
+
+        fn pending(&self) -> Vec<ProductSuggestion> {
+            self.suggestions.lock().unwrap().iter().filter(|s| s.status == SuggestionStatus::Pending).cloned().collect()
+        }
+    }
+
+    #[async_trait]
+    impl ProductLifecycleRepository for MemoryLifecycles {
+        async fn automation_mode(&self, _tenant_id: Uuid) -> Result<Option<LifecycleAutomationMode>> {
+            Ok(*self.mode.lock().unwrap())
+        }
+
+        async fn set_automation_mode(&self, _tenant_id: Uuid, mode: LifecycleAutomationMode, _updated_by: Uuid) -> Result<()> {
+            *self.mode.lock().unwrap() = Some(mode);
+            Ok(())
+        }
+
+        async fn lifecycle_candidates(&self, _tenant_id: Uuid, product_id: Option<Uuid>) -> Result<Vec<LifecycleCandidate>> {
+            Ok(self
+                .candidates
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|c| product_id.is_none_or(|id| c.product_id == id))
+                .cloned()
+                .collect())
+        }
+
+        async fn lifecycle_sales(&self, _tenant_id: Uuid, _now: DateTime<Utc>, _window_days: i64) -> Result<LifecycleSales> {
+            Ok(self.sales.clone())
+        }
+
+        async fn record_stage_change(&self, tenant_id: Uuid, change: &StageChange) -> Result<ProductLifecycle> {
+            let mut candidates = self.candidates.lock().unwrap();
+            let candidate = candidates.iter_mut().find(|c| c.product_id == change.product_id).unwrap();
+            let mut lifecycle = candidate.lifecycle.clone().unwrap_or_else(|| lifecycle(tenant_id, change.product_id, change.to));
+            lifecycle.previous_stage = Some(candidate.stage());
+            lifecycle.stage = change.to;
+            lifecycle.stage_changed_at = change.changed_at;
+            lifecycle.stage_set_by_automation = !change.source.is_manual();
+            lifecycle.stage_evidence = change.evidence.clone();
+            candidate.lifecycle = Some(lifecycle.clone());
+            self.changes.lock().unwrap().push(change.clone());
+            Ok(lifecycle)
+        }
+
+        async fn propose_stage_change(&self, suggestion: &ProductSuggestion) -> Result<bool> {
+            let mut suggestions = self.suggestions.lock().unwrap();
+            let pending = suggestions.iter_mut().filter(|s| {
+                s.product_id == suggestion.product_id && s.status == SuggestionStatus::Pending
+            });
+            for existing in pending {
+                if existing.suggested_value["stage"] == suggestion.suggested_value["stage"] {
+                    return Ok(false);
+                }
+                existing.status = SuggestionStatus::Expired;
+            }
+            suggestions.push(suggestion.clone());
+            Ok(true)
+        }
+    }
+
+    fn lifecycle(tenant_id: Uuid, product_id: Uuid, stage: LifecycleStage) -> ProductLifecycle {
+        let now = Utc::now();
+        ProductLifecycle {
+            id: Uuid::new_v4(),
+            product_id,
+            tenant_id,
+            stage,
+            stage_changed_at: now,
+            previous_stage: None,
+            stage_set_by_automation: false,
+            stage_evidence: None,
+            launched_at: None,
+            time_to_market: None,
+            development_cost: None,
+            total_revenue: 0,
+            units_produced: 0,
+            units_sold: 0,
+            planned_eol_date: None,
+            replacement_product_id: None,
+            sunset_strategy: None,
+            total_carbon_footprint: None,
+            materials_recycled: None,
+            waste_generated: None,
+            notes: None,
+            created_at: now,
+            updated_at: now,
+            created_by: Uuid::nil(),
+            updated_by: Uuid::nil(),
+        }
+    }
+
+    struct Fixture {
+        repository: Arc<MemoryLifecycles>,
+        service: DefaultLifecycleAutomationService,
+        /// Launched in 2022, never took off, still in Launch
+        stale_launch: Uuid,
+        /// Launched last month, selling more and more
+        rising: Uuid,
+    }
+
+    fn fixture(mode: Option<LifecycleAutomationMode>) -> Fixture {
+        let tenant_id = Uuid::new_v4();
+        let now = Utc::now();
+        let (stale_launch, rising) = (Uuid::new_v4(), Uuid::new_v4());
+        let candidate = |product_id, age_days| LifecycleCandidate {
+            product_id,
+            product_created_at: now - Duration::days(age_days),
+            product_updated_at: now - Duration::days(1),
+            lifecycle: None,
+        };
+
+        let mut sales = LifecycleSales {
+            tenant_revenue_recent: 1_000_000,
+            tenant_revenue_prior: 1_000_000,
+            ..Default::default()
+        };
+        let units = |units_prior, units_recent| ProductSales {
+            units_prior,
+            units_recent,
+            revenue_prior: units_prior * 100,
+            revenue_recent: units_recent * 100,
+        };
+        sales.products.insert(stale_launch, units(12, 11));
+        sales.products.insert(rising, units(0, 40));
+
+        let repository = Arc::new(MemoryLifecycles {
+            mode: Mutex::new(mode),
+            candidates: Mutex::new(vec![candidate(stale_launch, 1000), candidate(rising, 30)]),
+            sales,
+            ..Default::default()
+        });
+        let context = TenantContext::new(tenant_id, "acme".to_string(), Uuid::nil());
+        let service = DefaultLifecycleAutomationService::new(repository.clone(), context, LifecycleRules::default());
+        Fixture {
+            repository,
+            service,
+            stale_launch,
+            rising,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_suggest_mode_proposes_for_review() {
+        let f = fixture(None);
+        assert_eq!(f.service.automation_mode().await.unwrap(), LifecycleAutomationMode::Suggest);
+
+        let run = f.service.run(Utc::now()).await.unwrap();
+        assert_eq!((run.evaluated, run.suggested, run.applied), (2, 2, 0));
+        assert!(f.repository.changes.lock().unwrap().is_empty());
+        assert_eq!(f.repository.stage(f.stale_launch), LifecycleStage::Launch);
+
+        let pending = f.repository.pending();
+        let stale = pending.iter().find(|s| s.product_id == f.stale_launch).unwrap();
+        assert_eq!(stale.suggestion_type, LIFECYCLE_STAGE);
+        assert_eq!(stale.current_value, "launch");
+        assert_eq!(stale.suggested_value["stage"], "maturity");
+        assert_eq!(stale.suggested_value["evidence"]["rule"], RULE_INTRODUCTION_AGE);
+        assert_eq!(stale.suggested_value["evidence"]["days_since_launch"], 1000);
+
+        // The next run does not propose the same transitions again
+        let again = f.service.run(Utc::now()).await.unwrap();
+        assert_eq!(again.suggested, 0);
+        assert_eq!(f.repository.pending().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_auto_mode_applies_with_evidence() {
+        let f = fixture(Some(LifecycleAutomationMode::Auto));
+
+        let run = f.service.run(Utc::now()).await.unwrap();
+        assert_eq!((run.suggested, run.applied), (0, 2));
+        assert!(f.repository.pending().is_empty());
+        assert_eq!(f.repository.stage(f.stale_launch), LifecycleStage::Maturity);
+        assert_eq!(f.repository.stage(f.rising), LifecycleStage::Growth);
+
+        let evaluation = f.service.evaluate_product(f.rising, Utc::now()).await.unwrap();
+        let lifecycle = evaluation.lifecycle.unwrap();
+        assert!(lifecycle.stage_set_by_automation);
+        assert_eq!(lifecycle.previous_stage, Some(LifecycleStage::Launch));
+        let evidence = lifecycle.stage_evidence.unwrap();
+        assert_eq!((evidence["rule"].as_str(), evidence["units_recent"].as_i64()), (Some(RULE_SALES_GROWTH), Some(40)));
+
+        let changes = f.repository.changes.lock().unwrap();
+        assert!(changes.iter().all(|c| c.source == StageChangeSource::Automation));
+    }
+
+    #[tokio::test]
+    async fn test_off_mode_changes_nothing() {
+        let f = fixture(None);
+        f.service.set_automation_mode(LifecycleAutomationMode::Off).await.unwrap();
+
+        let run = f.service.run(Utc::now()).await.unwrap();
+        assert_eq!(run.evaluated, 0);
+        assert!(f.repository.pending().is_empty() && f.repository.changes.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_manual_stage_wins_within_the_freeze_window() {
+        let f = fixture(Some(LifecycleAutomationMode::Auto));
+        f.service.set_stage(f.stale_launch, LifecycleStage::Launch).await.unwrap();
+
+        let run = f.service.run(Utc::now()).await.unwrap();
+        assert_eq!((run.frozen, run.applied), (1, 1));
+        assert_eq!(f.repository.stage(f.stale_launch), LifecycleStage::Launch);
+        assert!(f.service.evaluate_product(f.stale_launch, Utc::now()).await.unwrap().frozen);
+
+        // Once the freeze window passed the rules apply again
+        let later = Utc::now() + Duration::days(LifecycleRules::default().manual_freeze_days + 1);
+        let run = f.service.run(later).await.unwrap();
+        assert_eq!(run.frozen, 0);
+        assert_eq!(f.repository.stage(f.stale_launch), LifecycleStage::Maturity);
+    }
+
+    #[tokio::test]
+    async fn test_set_stage_needs_the_product() {
+        let f = fixture(None);
+        let error = f.service.set_stage(Uuid::new_v4(), LifecycleStage::Growth).await.unwrap_err();
+        assert_eq!(error.code, ErrorCode::NotFound);
+    }
+}
@@ -4,6 +4,7 @@
 //! including products, categories, pricing, and variants.
 
 use chrono::{DateTime, Utc};
+use erp_core::error::{Error, ErrorCode, Result};
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 use uuid::Uuid;
@@ -32,6 +33,7 @@ impl Default for ProductStatus {
 
 /// Product type enumeration
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::Type)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[sqlx(type_name = "product_type", rename_all = "snake_case")]
 pub enum ProductType {
     /// Physical product that can be stocked
@@ -54,6 +56,7 @@ impl Default for ProductType {
 
 /// Unit of measure for products
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::Type)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[sqlx(type_name = "unit_of_measure", rename_all = "snake_case")]
 pub enum UnitOfMeasure {
     /// Piece/each
@@ -154,6 +157,9 @@ pub struct Product {
 
     // Inventory
     pub is_tracked: bool,
+    /// Stock is tracked per serial number (see [`crate::inventory::serial`])
+    #[serde(default)]
+    pub is_serialized: bool,
     pub current_stock: Option<i32>,
     pub min_stock_level: Option<i32>,
     pub max_stock_level: Option<i32>,
@@ -215,6 +221,7 @@ impl Product {
             cost_price: None,
             list_price: None,
             is_tracked: true,
+            is_serialized: false,
             current_stock: Some(0),
             min_stock_level: None,
             max_stock_level: None,
@@ -288,6 +295,135 @@ impl Product {
             None
         }
     }
+
+    /// Apply the fields set in `request`. The barcode is left alone: it has to
+    /// be checked against the tenant's other barcodes, which the service does.
+    pub fn apply_update(&mut self, request: UpdateProductRequest) -> Result<()> {
+        if let Some(name) = request.name {
+            if name.trim().is_empty() {
+                return Err(Error::new(ErrorCode::ValidationFailed, "Product name cannot be empty"));
+            }
+            self.name = name;
+        }
+
+        if let Some(description) = request.description {
+            self.description = Some(description);
+        }
+
+        if let Some(category_id) = request.category_id {
+            self.category_id = Some(category_id);
+        }
+
+        if let Some(product_type) = request.product_type {
+            self.product_type = product_type;
+        }
+
+        if let Some(status) = request.status {
+            self.status = status;
+        }
+
+        if let Some(unit_of_measure) = request.unit_of_measure {
+            self.unit_of_measure = unit_of_measure;
+        }
+
+        if let Some(base_price) = request.base_price {
+            if base_price < 0 {
+                return Err(Error::new(ErrorCode::ValidationFailed, "Base price cannot be negative"));
+            }
+            self.base_price = base_price;
+        }
+
+        if let Some(cost_price) = request.cost_price {
+            if cost_price < 0 {
+                return Err(Error::new(ErrorCode::ValidationFailed, "Cost price cannot be negative"));
+            }
+            self.cost_price = Some(cost_price);
+        }
+
+        if let Some(list_price) = request.list_price {
+            if list_price < 0 {
+                return Err(Error::new(ErrorCode::ValidationFailed, "List price cannot be negative"));
+            }
+            self.list_price = Some(list_price);
+        }
+
+        if let Some(is_tracked) = request.is_tracked {
+            self.is_tracked = is_tracked;
+        }
+
+        if let Some(is_serialized) = request.is_serialized {
+            self.is_serialized = is_serialized;
+        }
+
+        if let Some(current_stock) = request.current_stock {
+            if current_stock < 0 {
+                return Err(Error::new(ErrorCode::ValidationFailed, "Stock level cannot be negative"));
+            }
+            self.current_stock = Some(current_stock);
+        }
+
+        if let Some(min_stock_level) = request.min_stock_level {
+            self.min_stock_level = Some(min_stock_level);
+        }
+
+        if let Some(max_stock_level) = request.max_stock_level {
+            self.max_stock_level = Some(max_stock_level);
+        }
+
+        if let Some(reorder_point) = request.reorder_point {
+            self.reorder_point = Some(reorder_point);
+        }
+
+        if let Some(primary_supplier_id) = request.primary_supplier_id {
+            self.primary_supplier_id = Some(primary_supplier_id);
+        }
+
+        if let Some(weight) = request.weight {
+            self.weight = Some(weight);
+        }
+
+        if let Some(dimensions_length) = request.dimensions_length {
+            self.dimensions_length = Some(dimensions_length);
+        }
+
+        if let Some(dimensions_width) = request.dimensions_width {
+            self.dimensions_width = Some(dimensions_width);
+        }
+
+        if let Some(dimensions_height) = request.dimensions_height {
+            self.dimensions_height = Some(dimensions_height);
+        }
+
+        if let Some(brand) = request.brand {
+            self.brand = Some(brand);
+        }
+
+        if let Some(manufacturer) = request.manufacturer {
+            self.manufacturer = Some(manufacturer);
+        }
+
+        if let Some(model_number) = request.model_number {
+            self.model_number = Some(model_number);
+        }
+
+        if let Some(warranty_months) = request.warranty_months {
+            self.warranty_months = Some(warranty_months);
+        }
+
+        if let Some(is_featured) = request.is_featured {
+            self.is_featured = is_featured;
+        }
+
+        if let Some(tags) = request.tags {
+            self.tags = Some(tags);
+        }
+
+        if let Some(notes) = request.notes {
+            self.notes = Some(notes);
+        }
+
+        Ok(())
+    }
 }
 
 /// Product category for hierarchical organization
@@ -447,6 +583,7 @@ pub struct ProductSupplier {
 
 /// Request/Response DTOs for API
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct CreateProductRequest {
     pub sku: String,
     pub name: String,
@@ -458,6 +595,8 @@ pub struct CreateProductRequest {
     pub currency: String,
     pub cost_price: Option<i64>,
     pub is_tracked: bool,
+    #[serde(default)]
+    pub is_serialized: bool,
     pub current_stock: Option<i32>,
     pub min_stock_level: Option<i32>,
     pub reorder_point: Option<i32>,
@@ -467,6 +606,9 @@ pub struct CreateProductRequest {
     pub brand: Option<String>,
     pub manufacturer: Option<String>,
     pub tags: Option<Vec<String>>,
+    /// Every received batch gets an incoming-goods inspection
+    #[serde(default)]
+    pub inspection_required: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -481,6 +623,7 @@ pub struct UpdateProductRequest {
     pub cost_price: Option<i64>,
     pub list_price: Option<i64>,
     pub is_tracked: Option<bool>,
+    pub is_serialized: Option<bool>,
     pub current_stock: Option<i32>,
     pub min_stock_level: Option<i32>,
     pub max_stock_level: Option<i32>,
@@ -616,6 +759,9 @@ pub struct ProductAttributes {
     pub quality_grade: Option<String>,
     pub certification_expiry: Option<DateTime<Utc>>,
     pub batch_tracking_required: bool,
+    /// Every received batch gets an incoming-goods inspection
+    #[serde(default)]
+    pub inspection_required: bool,
 
     // Advanced Properties
     pub shelf_life_days: Option<i32>,
@@ -768,7 +914,8 @@ pub struct ProductBatch {
     pub updated_by: Uuid,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, sqlx::Type)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
 #[sqlx(type_name = "quality_status", rename_all = "snake_case")]
 pub enum QualityStatus {
     Pending,
@@ -778,6 +925,29 @@ pub enum QualityStatus {
     Recalled,
 }
 
+impl QualityStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            QualityStatus::Pending => "pending",
+            QualityStatus::Passed => "passed",
+            QualityStatus::Failed => "failed",
+            QualityStatus::Quarantined => "quarantined",
+            QualityStatus::Recalled => "recalled",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "pending" => Some(QualityStatus::Pending),
+            "passed" => Some(QualityStatus::Passed),
+            "failed" => Some(QualityStatus::Failed),
+            "quarantined" => Some(QualityStatus::Quarantined),
+            "recalled" => Some(QualityStatus::Recalled),
+            _ => None,
+        }
+    }
+}
+
 /// Product analytics and performance metrics
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct ProductAnalytics {
@@ -836,6 +1006,13 @@ pub struct ProductLifecycle {
     pub stage: LifecycleStage,
     pub stage_changed_at: DateTime<Utc>,
     pub previous_stage: Option<LifecycleStage>,
+    /// Whether the lifecycle automation set the current stage; stages set by
+    /// hand are not changed by it for the configured freeze window
+    pub stage_set_by_automation: bool,
+    /// Metric values the automation changed the stage on
+    pub stage_evidence: Option<serde_json::Value>,
+    /// When the product reached the market; its creation when not recorded
+    pub launched_at: Option<DateTime<Utc>>,
 
     // Lifecycle Metrics
     pub time_to_market: Option<i32>, // days from concept to launch
@@ -862,12 +1039,13 @@ pub struct ProductLifecycle {
     pub updated_by: Uuid,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, sqlx::Type)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
 #[sqlx(type_name = "lifecycle_stage", rename_all = "snake_case")]
 pub enum LifecycleStage {
     Concept,
     Development,
     Testing,
+    /// Introduction to the market
     Launch,
     Growth,
     Maturity,
@@ -876,6 +1054,37 @@ pub enum LifecycleStage {
     Retired,
 }
 
+impl LifecycleStage {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LifecycleStage::Concept => "concept",
+            LifecycleStage::Development => "development",
+            LifecycleStage::Testing => "testing",
+            LifecycleStage::Launch => "launch",
+            LifecycleStage::Growth => "growth",
+            LifecycleStage::Maturity => "maturity",
+            LifecycleStage::Decline => "decline",
+            LifecycleStage::EndOfLife => "end_of_life",
+            LifecycleStage::Retired => "retired",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "concept" => Some(LifecycleStage::Concept),
+            "development" => Some(LifecycleStage::Development),
+            "testing" => Some(LifecycleStage::Testing),
+            "launch" => Some(LifecycleStage::Launch),
+            "growth" => Some(LifecycleStage::Growth),
+            "maturity" => Some(LifecycleStage::Maturity),
+            "decline" => Some(LifecycleStage::Decline),
+            "end_of_life" => Some(LifecycleStage::EndOfLife),
+            "retired" => Some(LifecycleStage::Retired),
+            _ => None,
+        }
+    }
+}
+
 /// Advanced search and filtering capabilities
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AdvancedProductSearch {
@@ -0,0 +1,647 @@
+//! # Price Lists
+//!
+//! A price list prices products for a slice of the business: a customer group
+//! ("wholesale"), a sales channel ("webshop"), or both, in one currency and
+//! optionally within a validity window. Each line prices one product, or one
+//! variant of it, either at a fixed price or as a discount from the base price.
+//!
+//! ## Resolution
+//!
+//! For a product and a [`PriceContext`], a list applies when it is active,
+//! in the requested currency, valid at the context's time (`valid_from`
+//! inclusive, `valid_until` exclusive) and its channel and customer group are
+//! either unset or equal to the context's. Of the applicable lists with a line
+//! for the product, the highest `priority` wins; within a list a variant line
+//! beats the product line. Without a match the base price applies.
+//!
+//! Two lists with the same priority, currency and overlapping validity whose
+//! scopes can both apply to one request are rejected on save, so resolution
+//! never has to pick between equals.
+
+use super::repository::PriceContext;
+use super::service::{Discount, EffectivePrice};
+use crate::inventory::stocktake::{detect_delimiter, split_record};
+use chrono::{DateTime, Utc};
+use erp_core::error::{Error, ErrorCode, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PriceList {
+    pub id: Uuid,
+    pub tenant_id: Uuid,
+    pub name: String,
+    /// ISO 4217 code, upper case
+    pub currency: String,
+    /// `None` applies to every channel
+    pub channel: Option<String>,
+    /// `None` applies to every customer
+    pub customer_group: Option<String>,
+    /// Higher wins
+    pub priority: i32,
+    pub valid_from: Option<DateTime<Utc>>,
+    /// Exclusive
+    pub valid_until: Option<DateTime<Utc>>,
+    pub is_active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub created_by: Uuid,
+    pub updated_by: Uuid,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PriceListRequest {
+    pub name: String,
+    pub currency: String,
+    pub channel: Option<String>,
+    pub customer_group: Option<String>,
+    #[serde(default)]
+    pub priority: i32,
+    pub valid_from: Option<DateTime<Utc>>,
+    pub valid_until: Option<DateTime<Utc>>,
+    #[serde(default = "default_active")]
+    pub is_active: bool,
+}
+
+fn default_active() -> bool {
+    true
+}
+
+/// How a line prices its product
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LinePricing {
+    /// Price in cents
+    Fixed { price: i64 },
+    /// Percent off the base price
+    Discount { percent: f64 },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PriceListLine {
+    pub price_list_id: Uuid,
+    pub product_id: Uuid,
+    /// `None` prices the product and all its variants
+    pub variant_id: Option<Uuid>,
+    pub pricing: LinePricing,
+}
+
+/// Base price of a product, or of one of its variants
+#[derive(Debug, Clone)]
+pub struct PriceBase {
+    pub product_id: Uuid,
+    pub variant_id: Option<Uuid>,
+    /// Product base price in cents
+    pub base_price: i64,
+    /// Added to the product price for the variant
+    pub variant_adjustment: i64,
+    pub currency: String,
+}
+
+/// A product or variant a line file can price, by SKU
+#[derive(Debug, Clone)]
+pub struct PriceTarget {
+    pub sku: String,
+    /// `None` for the product itself
+    pub variant_sku: Option<String>,
+    pub product_id: Uuid,
+    pub variant_id: Option<Uuid>,
+}
+
+/// Normalize and check a list before it is stored
+pub fn validate_price_list(list: &mut PriceList) -> Result<()> {
+    list.name = list.name.trim().to_string();
+    list.currency = list.currency.trim().to_uppercase();
+    list.channel = normalize_scope(list.channel.take());
+    list.customer_group = normalize_scope(list.customer_group.take());
+
+    if list.name.is_empty() {
+        return Err(Error::new(ErrorCode::ValidationFailed, "Price list name is required"));
+    }
+    if list.currency.len() != 3 || !list.currency.chars().all(|c| c.is_ascii_alphabetic()) {
+        return Err(Error::new(
+            ErrorCode::ValidationFailed,
+            format!("Invalid currency '{}'", list.currency),
+        ));
+    }
+    if let (Some(from), Some(until)) = (list.valid_from, list.valid_until) {
+        if until <= from {
+            return Err(Error::new(ErrorCode::ValidationFailed, "valid_until must be after valid_from"));
+        }
+    }
+    Ok(())
+}
+
+/// Channels and groups compare case-insensitively; blank means unset
+fn normalize_scope(value: Option<String>) -> Option<String> {
+    value.map(|v| v.trim().to_lowercase()).filter(|v| !v.is_empty())
+}
+
+fn scopes_intersect(a: &Option<String>, b: &Option<String>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => a == b,
+        _ => true,
+    }
+}
+
+fn windows_overlap(a: &PriceList, b: &PriceList) -> bool {
+    let starts_before_b_ends = match (a.valid_from, b.valid_until) {
+        (Some(from), Some(until)) => from < until,
+        _ => true,
+    };
+    let b_starts_before_a_ends = match (b.valid_from, a.valid_until) {
+        (Some(from), Some(until)) => from < until,
+        _ => true,
+    };
+    starts_before_b_ends && b_starts_before_a_ends
+}
+
+/// The active list among `existing` that `list` would tie with, if any
+pub fn find_overlap<'a>(list: &PriceList, existing: &'a [PriceList]) -> Option<&'a PriceList> {
+    if !list.is_active {
+        return None;
+    }
+    existing.iter().find(|other| {
+        other.id != list.id
+            && other.is_active
+            && other.priority == list.priority
+            && other.currency == list.currency
+            && scopes_intersect(&other.channel, &list.channel)
+            && scopes_intersect(&other.customer_group, &list.customer_group)
+            && windows_overlap(other, list)
+    })
+}
+
+/// Whether `list` may price a request in `currency` with this context
+pub fn list_applies(list: &PriceList, currency: &str, context: &PriceContext) -> bool {
+    let at = context.date_time;
+    let matches = |scope: &Option<String>, value: &Option<String>| match scope {
+        None => true,
+        Some(scope) => value.as_deref().is_some_and(|v| v.trim().eq_ignore_ascii_case(scope)),
+    };
+
+    list.is_active
+        && list.currency.eq_ignore_ascii_case(currency)
+        && list.valid_from.is_none_or(|from| from <= at)
+        && list.valid_until.is_none_or(|until| at < until)
+        && matches(&list.channel, &context.channel)
+        && matches(&list.customer_group, &context.customer_group)
+}
+
+/// Price of `base` under `context`.
+///
+/// `lists` are the tenant's lists and `lines` their lines for the product.
+/// Fails with `ValidationFailed` when a currency other than the product's is
+/// requested and no list prices the product in it, or when the list that does
+/// would have to apply a discount or a variant adjustment in the product's
+/// currency to it; such a product needs a fixed price per variant there.
+pub fn resolve_price(
+    base: &PriceBase,
+    lists: &[PriceList],
+    lines: &[PriceListLine],
+    context: &PriceContext,
+) -> Result<EffectivePrice> {
+    let currency = context
+        .currency
+        .as_deref()
+        .map(|c| c.trim().to_uppercase())
+        .unwrap_or_else(|| base.currency.to_uppercase());
+    let base_price = base.base_price + base.variant_adjustment;
+
+    let by_id: HashMap<Uuid, &PriceList> = lists
+        .iter()
+        .filter(|list| list_applies(list, &currency, context))
+        .map(|list| (list.id, list))
+        .collect();
+
+    let best = lines
+        .iter()
+        .filter(|line| line.product_id == base.product_id)
+        .filter(|line| line.variant_id.is_none() || line.variant_id == base.variant_id)
+        .filter_map(|line| by_id.get(&line.price_list_id).map(|list| (*list, line)))
+        .max_by_key(|(list, line)| (list.priority, line.variant_id.is_some(), std::cmp::Reverse(list.id)));
+
+    let Some((list, line)) = best else {
+        if !currency.eq_ignore_ascii_case(&base.currency) {
+            return Err(Error::new(
+                ErrorCode::ValidationFailed,
+                format!("No price list prices product {} in {}; its base currency is {}", base.product_id, currency, base.currency),
+            ));
+        }
+        return Ok(EffectivePrice {
+            base_price,
+            discounts: vec![],
+            final_price: base_price,
+            currency,
+            valid_until: None,
+            pricing_rules_applied: vec!["base_price".to_string()],
+        });
+    };
+
+    // Product prices and variant adjustments are in the product's currency; a
+    // list in another one can replace them but nothing can be derived from them
+    let foreign = !currency.eq_ignore_ascii_case(&base.currency);
+    let not_in_currency = |what: &str| {
+        Error::new(
+            ErrorCode::ValidationFailed,
+            format!(
+                "Price list {} prices product {} in {}, but its {} is in {}",
+                list.name, base.product_id, currency, what, base.currency
+            ),
+        )
+    };
+
+    let (final_price, discounts) = match line.pricing {
+        // A product-level fixed price replaces the product price; the variant still adjusts it
+        LinePricing::Fixed { .. } if foreign && line.variant_id.is_none() && base.variant_adjustment != 0 => {
+            return Err(not_in_currency("variant adjustment"));
+        }
+        LinePricing::Fixed { price } if line.variant_id.is_none() => (price + base.variant_adjustment, vec![]),
+        LinePricing::Fixed { price } => (price, vec![]),
+        LinePricing::Discount { .. } if foreign => return Err(not_in_currency("price to discount")),
+        LinePricing::Discount { percent } => {
+            let amount = (base_price as f64 * percent / 100.0).round() as i64;
+            let discount = Discount {
+                discount_type: "price_list".to_string(),
+                amount,
+                percentage: Some(percent),
+                reason: list.name.clone(),
+            };
+            (base_price - amount, vec![discount])
+        }
+    };
+
+    Ok(EffectivePrice {
+        // There is no base price in a foreign currency but the list's own
+        base_price: if foreign { final_price } else { base_price },
+        discounts,
+        final_price: final_price.max(0),
+        currency,
+        valid_until: list.valid_until,
+        pricing_rules_applied: vec![format!("price_list:{}", list.id)],
+    })
+}
+
+/// One readable row of a line file, products still by SKU
+#[derive(Debug, Clone, PartialEq)]
+pub struct PriceLineRow {
+    pub line_number: usize,
+    pub sku: String,
+    pub variant_sku: Option<String>,
+    pub pricing: LinePricing,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PriceLineError {
+    pub line_number: usize,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ParsedPriceLines {
+    pub rows: Vec<PriceLineRow>,
+    pub errors: Vec<PriceLineError>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PriceLineImportResult {
+    pub price_list_id: Uuid,
+    pub lines_loaded: usize,
+    /// Rows that could not be read or matched to a product
+    pub errors: Vec<PriceLineError>,
+}
+
+/// Parse a line file for a list in `currency`.
+///
+/// Columns: `sku,variant_sku,price,discount_percent,currency`. Exactly one of
+/// `price` (decimal, e.g. `12.50`) and `discount_percent` is set; `currency`
+/// may be blank but must otherwise match the list. A header row is skipped
+/// and `;` is accepted as the delimiter. Unreadable rows are reported in
+/// `errors` instead of failing the file; a later row for the same product
+/// and variant replaces an earlier one.
+pub fn parse_price_lines(content: &str, currency: &str) -> ParsedPriceLines {
+    let mut parsed = ParsedPriceLines::default();
+    let delimiter = detect_delimiter(content);
+    let mut seen: HashMap<(String, Option<String>), usize> = HashMap::new();
+
+    for (index, raw) in content.lines().enumerate() {
+        let line_number = index + 1;
+        let raw = raw.trim();
+        if raw.is_empty() {
+            continue;
+        }
+
+        let fields = split_record(raw, delimiter);
+        if parsed.rows.is_empty() && parsed.errors.is_empty() && fields[0].eq_ignore_ascii_case("sku") {
+            continue;
+        }
+
+        match parse_line_fields(&fields, currency) {
+            Ok((sku, variant_sku, pricing)) => {
+                let row = PriceLineRow {
+                    line_number,
+                    sku,
+                    variant_sku,
+                    pricing,
+                };
+                match seen.get(&(row.sku.clone(), row.variant_sku.clone())) {
+                    Some(&position) => parsed.rows[position] = row,
+                    None => {
+                        seen.insert((row.sku.clone(), row.variant_sku.clone()), parsed.rows.len());
+                        parsed.rows.push(row);
+                    }
+                }
+            }
+            Err(message) => parsed.errors.push(PriceLineError { line_number, message }),
+        }
+    }
+
+    parsed
+}
+
+fn parse_line_fields(fields: &[String], currency: &str) -> std::result::Result<(String, Option<String>, LinePricing), String> {
+    if !(4..=5).contains(&fields.len()) {
+        return Err(format!("Expected 4 or 5 fields, found {}", fields.len()));
+    }
+
+    let sku = fields[0].clone();
+    if sku.is_empty() {
+        return Err("SKU is empty".to_string());
+    }
+    let variant_sku = Some(fields[1].clone()).filter(|v| !v.is_empty());
+    if let Some(line_currency) = fields.get(4).filter(|c| !c.is_empty()) {
+        if !line_currency.eq_ignore_ascii_case(currency) {
+            return Err(format!("Currency {} does not match the price list currency {}", line_currency, currency));
+        }
+    }
+
+    let pricing = match (fields[2].as_str(), fields[3].as_str()) {
+        (price, "") if !price.is_empty() => LinePricing::Fixed {
+            price: parse_cents(price).ok_or_else(|| format!("Invalid price '{}'", price))?,
+        },
+        ("", percent) if !percent.is_empty() => LinePricing::Discount {
+            percent: percent
+                .trim_end_matches('%')
+                .parse::<f64>()
+                .ok()
+                .filter(|p| (0.0..=100.0).contains(p))
+                .ok_or_else(|| format!("Invalid discount '{}'", percent))?,
+        },
+        _ => return Err("Set exactly one of price and discount_percent".to_string()),
+    };
+
+    Ok((sku, variant_sku, pricing))
+}
+
+/// `12.5` or `12,50` to 1250 cents; negative amounts and more than two decimals are refused
+fn parse_cents(value: &str) -> Option<i64> {
+    let value = value.replace(',', ".");
+    let (whole, fraction) = value.split_once('.').unwrap_or((&value, ""));
+    if whole.is_empty() || fraction.len() > 2 || !whole.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    if !fraction.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let cents = format!("{:0<2}", fraction).parse::<i64>().ok()?;
+    whole.parse::<i64>().ok()?.checked_mul(100)?.checked_add(cents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn at(value: &str) -> DateTime<Utc> {
+        value.parse().unwrap()
+    }
+
+    fn list(name: &str, priority: i32, channel: Option<&str>, group: Option<&str>) -> PriceList {
+        PriceList {
+            id: Uuid::new_v4(),
+            tenant_id: Uuid::nil(),
+            name: name.to_string(),
+            currency: "EUR".to_string(),
+            channel: channel.map(str::to_string),
+            customer_group: group.map(str::to_string),
+            priority,
+            valid_from: None,
+            valid_until: None,
+            is_active: true,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            created_by: Uuid::nil(),
+            updated_by: Uuid::nil(),
+        }
+    }
+
+    fn line(list: &PriceList, product_id: Uuid, variant_id: Option<Uuid>, pricing: LinePricing) -> PriceListLine {
+        PriceListLine {
+            price_list_id: list.id,
+            product_id,
+            variant_id,
+            pricing,
+        }
+    }
+
+    fn base(product_id: Uuid) -> PriceBase {
+        PriceBase {
+            product_id,
+            variant_id: None,
+            base_price: 10_000,
+            variant_adjustment: 0,
+            currency: "EUR".to_string(),
+        }
+    }
+
+    fn context(channel: Option<&str>, group: Option<&str>, date_time: DateTime<Utc>) -> PriceContext {
+        PriceContext {
+            customer_tier: None,
+            quantity: None,
+            location: None,
+            date_time,
+            customer_group: group.map(str::to_string),
+            channel: channel.map(str::to_string),
+            currency: None,
+        }
+    }
+
+    #[test]
+    fn test_highest_priority_applicable_list_wins() {
+        let product = Uuid::new_v4();
+        let wholesale = list("Wholesale", 10, None, Some("wholesale"));
+        let webshop = list("Webshop", 5, Some("webshop"), None);
+        let everyone = list("Everyone", 1, None, None);
+        let lines = vec![
+            line(&wholesale, product, None, LinePricing::Discount { percent: 12.0 }),
+            line(&webshop, product, None, LinePricing::Fixed { price: 9_500 }),
+            line(&everyone, product, None, LinePricing::Fixed { price: 9_900 }),
+        ];
+        let lists = vec![wholesale.clone(), webshop.clone(), everyone];
+        let now = Utc::now();
+
+        let price = resolve_price(&base(product), &lists, &lines, &context(Some("webshop"), Some("Wholesale"), now)).unwrap();
+        assert_eq!(price.final_price, 8_800);
+        assert_eq!(price.discounts[0].amount, 1_200);
+        assert_eq!(price.pricing_rules_applied, vec![format!("price_list:{}", wholesale.id)]);
+
+        let price = resolve_price(&base(product), &lists, &lines, &context(Some("webshop"), None, now)).unwrap();
+        assert_eq!(price.final_price, 9_500);
+
+        let price = resolve_price(&base(product), &lists, &lines, &context(Some("pos"), Some("retail"), now)).unwrap();
+        assert_eq!(price.final_price, 9_900);
+
+        // No applicable list with a line for the product: base price
+        let price = resolve_price(&base(Uuid::new_v4()), &lists, &lines, &context(None, None, now)).unwrap();
+        assert_eq!((price.final_price, price.pricing_rules_applied[0].as_str()), (10_000, "base_price"));
+    }
+
+    #[test]
+    fn test_variant_line_beats_product_line_of_same_list() {
+        let (product, variant) = (Uuid::new_v4(), Uuid::new_v4());
+        let webshop = list("Webshop", 5, Some("webshop"), None);
+        let lines = vec![
+            line(&webshop, product, None, LinePricing::Fixed { price: 9_000 }),
+            line(&webshop, product, Some(variant), LinePricing::Fixed { price: 12_000 }),
+        ];
+        let lists = vec![webshop];
+        let ctx = context(Some("webshop"), None, Utc::now());
+
+        let red = PriceBase { variant_id: Some(variant), variant_adjustment: 500, ..base(product) };
+        assert_eq!(resolve_price(&red, &lists, &lines, &ctx).unwrap().final_price, 12_000);
+
+        // Other variants get the product line plus their own adjustment
+        let blue = PriceBase { variant_id: Some(Uuid::new_v4()), variant_adjustment: 500, ..base(product) };
+        assert_eq!(resolve_price(&blue, &lists, &lines, &ctx).unwrap().final_price, 9_500);
+    }
+
+    #[test]
+    fn test_validity_window_is_inclusive_start_exclusive_end() {
+        let product = Uuid::new_v4();
+        let mut promo = list("Spring promo", 20, None, None);
+        promo.valid_from = Some(at("2026-03-01T00:00:00Z"));
+        promo.valid_until = Some(at("2026-04-01T00:00:00Z"));
+        let lines = vec![line(&promo, product, None, LinePricing::Discount { percent: 25.0 })];
+        let lists = vec![promo];
+        let price_at = |time: &str| {
+            resolve_price(&base(product), &lists, &lines, &context(None, None, at(time))).unwrap().final_price
+        };
+
+        assert_eq!(price_at("2026-02-28T23:59:59Z"), 10_000);
+        assert_eq!(price_at("2026-03-01T00:00:00Z"), 7_500);
+        assert_eq!(price_at("2026-03-31T23:59:59Z"), 7_500);
+        assert_eq!(price_at("2026-04-01T00:00:00Z"), 10_000);
+    }
+
+    #[test]
+    fn test_overlapping_windows_at_same_priority_are_rejected() {
+        let mut march = list("March", 10, None, Some("wholesale"));
+        march.valid_from = Some(at("2026-03-01T00:00:00Z"));
+        march.valid_until = Some(at("2026-04-01T00:00:00Z"));
+
+        let mut april = list("April", 10, None, Some("wholesale"));
+        april.valid_from = march.valid_until;
+        assert!(find_overlap(&april, std::slice::from_ref(&march)).is_none(), "adjacent windows do not overlap");
+
+        april.valid_from = Some(at("2026-03-31T00:00:00Z"));
+        assert_eq!(find_overlap(&april, std::slice::from_ref(&march)).map(|l| l.id), Some(march.id));
+
+        // A channel list with no group can price wholesale customers too
+        let webshop = list("Webshop", 10, Some("webshop"), None);
+        assert!(find_overlap(&webshop, std::slice::from_ref(&march)).is_some());
+
+        let other_priority = list("Other", 11, None, Some("wholesale"));
+        let other_group = list("Retail", 10, None, Some("retail"));
+        let mut usd = list("USD", 10, None, Some("wholesale"));
+        usd.currency = "USD".to_string();
+        for candidate in [other_priority, other_group, usd] {
+            assert!(find_overlap(&candidate, std::slice::from_ref(&march)).is_none(), "{}", candidate.name);
+        }
+    }
+
+    #[test]
+    fn test_currency_mismatch_is_rejected() {
+        let product = Uuid::new_v4();
+        let eur = list("Wholesale", 10, None, Some("wholesale"));
+        let mut usd = list("US wholesale", 10, None, Some("wholesale"));
+        usd.currency = "USD".to_string();
+        let lines = vec![line(&eur, product, None, LinePricing::Fixed { price: 9_000 })];
+        let mut ctx = context(None, Some("wholesale"), Utc::now());
+        ctx.currency = Some("usd".to_string());
+
+        let lists = vec![eur.clone(), usd.clone()];
+        assert!(resolve_price(&base(product), &lists, &lines, &ctx).is_err());
+
+        let lines = vec![line(&usd, product, None, LinePricing::Fixed { price: 9_900 })];
+        let price = resolve_price(&base(product), &lists, &lines, &ctx).unwrap();
+        assert_eq!((price.final_price, price.currency.as_str()), (9_900, "USD"));
+
+        let mut discount = line(&usd, product, None, LinePricing::Discount { percent: 10.0 });
+        let err = resolve_price(&base(product), &lists, &[discount.clone()], &ctx).unwrap_err();
+        assert_eq!(err.code, ErrorCode::ValidationFailed);
+        // The same discount in the product's own currency is fine
+        discount.price_list_id = eur.id;
+        ctx.currency = None;
+        assert_eq!(resolve_price(&base(product), &lists, &[discount], &ctx).unwrap().final_price, 9_000);
+
+        let mut invalid = list("Bad", 1, None, None);
+        invalid.currency = "euro".to_string();
+        assert!(validate_price_list(&mut invalid).is_err());
+        let mut backwards = list("Backwards", 1, None, None);
+        backwards.valid_from = Some(Utc::now());
+        backwards.valid_until = Some(Utc::now() - Duration::days(1));
+        assert!(validate_price_list(&mut backwards).is_err());
+    }
+
+    #[test]
+    fn test_foreign_list_does_not_adjust_variants_in_the_product_currency() {
+        let product = Uuid::new_v4();
+        let mut usd = list("US", 1, None, None);
+        usd.currency = "USD".to_string();
+        let mut variant = base(product);
+        variant.variant_id = Some(Uuid::new_v4());
+        variant.variant_adjustment = 500;
+        let mut ctx = context(None, None, Utc::now());
+        ctx.currency = Some("USD".to_string());
+        let lists = vec![usd.clone()];
+
+        let product_line = vec![line(&usd, product, None, LinePricing::Fixed { price: 11_000 })];
+        let err = resolve_price(&variant, &lists, &product_line, &ctx).unwrap_err();
+        assert_eq!(err.code, ErrorCode::ValidationFailed);
+
+        // A fixed price for the variant itself needs no adjustment
+        let variant_line = vec![line(&usd, product, variant.variant_id, LinePricing::Fixed { price: 11_600 })];
+        let price = resolve_price(&variant, &lists, &variant_line, &ctx).unwrap();
+        assert_eq!((price.base_price, price.final_price, price.currency.as_str()), (11_600, 11_600, "USD"));
+
+        // So does a variant without one
+        variant.variant_adjustment = 0;
+        assert_eq!(resolve_price(&variant, &lists, &product_line, &ctx).unwrap().final_price, 11_000);
+    }
+
+    #[test]
+    fn test_csv_line_loader() {
+        const FILE: &str = "sku;variant_sku;price;discount_percent;currency\n\
+            SKU-1;;12,50;;EUR\n\
+            SKU-2;SKU-2-RED;;7.5%;\n\
+            SKU-3;;9.999;;\n\
+            SKU-4;;10;5;\n\
+            SKU-5;;10;;USD\n\
+            SKU-1;;11.00;;\n";
+        let parsed = parse_price_lines(FILE, "EUR");
+
+        assert_eq!(parsed.rows.len(), 2);
+        assert_eq!(parsed.rows[0].sku, "SKU-1");
+        assert_eq!(parsed.rows[0].pricing, LinePricing::Fixed { price: 1_100 }, "later row replaces earlier");
+        assert_eq!(parsed.rows[0].line_number, 7);
+        assert_eq!(parsed.rows[1].variant_sku.as_deref(), Some("SKU-2-RED"));
+        assert_eq!(parsed.rows[1].pricing, LinePricing::Discount { percent: 7.5 });
+
+        let failed: Vec<usize> = parsed.errors.iter().map(|e| e.line_number).collect();
+        assert_eq!(failed, vec![4, 5, 6]);
+        assert!(parsed.errors[2].message.contains("USD"));
+        assert_eq!(parse_cents("0.5"), Some(50));
+        assert_eq!(parse_cents("-1"), None);
+    }
+}
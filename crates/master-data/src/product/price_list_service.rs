@@ -0,0 +1,335 @@
+use async_trait::async_trait;
+use chrono::Utc;
+use erp_core::error::{Error, ErrorCode, Result};
+use std::collections::HashMap;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::product::price_list::*;
+use crate::product::repository::{PriceContext, PriceListRepository};
+use crate::product::service::EffectivePrice;
+use crate::types::TenantContext;
+
+/// Price lists per customer group and channel, and price resolution through them
+#[async_trait]
+pub trait PriceListService: Send + Sync {
+    async fn list_price_lists(&self) -> Result<Vec<PriceList>>;
+
+    async fn get_price_list(&self, id: Uuid) -> Result<PriceList>;
+
+    /// Fails with `ConflictError` if an active list of the same priority and
+    /// currency could apply to the same requests in an overlapping window
+    async fn create_price_list(&self, request: PriceListRequest) -> Result<PriceList>;
+
+    /// Same checks as [`create_price_list`](Self::create_price_list)
+    async fn update_price_list(&self, id: Uuid, request: PriceListRequest) -> Result<PriceList>;
+
+    async fn delete_price_list(&self, id: Uuid) -> Result<()>;
+
+    /// Load lines from a CSV file (see [`parse_price_lines`]). Rows that cannot
+    /// be read or whose SKU is unknown are reported; the others are stored.
+    async fn import_lines_csv(&self, id: Uuid, content: &str) -> Result<PriceLineImportResult>;
+
+    /// Price of a product, or one of its variants, under `context`
+    async fn effective_price(&self, product_id: Uuid, variant_id: Option<Uuid>, context: &PriceContext) -> Result<EffectivePrice>;
+}
+
+pub struct DefaultPriceListService {
+    repository: Arc<dyn PriceListRepository>,
+    tenant_context: TenantContext,
+}
+
+impl DefaultPriceListService {
+    pub fn new(repository: Arc<dyn PriceListRepository>, tenant_context: TenantContext) -> Self {
+        Self {
+            repository,
+            tenant_context,
+        }
+    }
+
+    async fn save(&self, mut list: PriceList) -> Result<PriceList> {
+        validate_price_list(&mut list)?;
+
+        let existing = self.repository.list_price_lists(self.tenant_context.tenant_id).await?;
+        if let Some(other) = find_overlap(&list, &existing) {
+            return Err(Error::new(
+                ErrorCode::ConflictError,
+                format!(
+                    "Price list '{}' has the same priority ({}) and currency and overlaps with '{}'",
+                    list.name, list.priority, other.name
+                ),
+            ));
+        }
+
+        self.repository.save_price_list(&list).await?;
+        Ok(list)
+    }
+}
+
+#[async_trait]
+impl PriceListService for DefaultPriceListService {
+    async fn list_price_lists(&self) -> Result<Vec<PriceList>> {
+        self.repository.list_price_lists(self.tenant_context.tenant_id).await
+    }
+
+    async fn get_price_list(&self, id: Uuid) -> Result<PriceList> {
+        self.repository
+            .get_price_list(self.tenant_context.tenant_id, id)
+            .await?
+            .ok_or_else(|| Error::new(ErrorCode::NotFound, format!("Price list {} not found", id)))
+    }
+
+    async fn create_price_list(&self, request: PriceListRequest) -> Result<PriceList> {
+        let now = Utc::now();
+        let user_id = self.tenant_context.user_id;
+        self.save(PriceList {
+            id: Uuid::new_v4(),
+            tenant_id: self.tenant_context.tenant_id,
+            name: request.name,
+            currency: request.currency,
+            channel: request.channel,
+            customer_group: request.customer_group,
+            priority: request.priority,
+            valid_from: request.valid_from,
+            valid_until: request.valid_until,
+            is_active: request.is_active,
+            created_at: now,
+            updated_at: now,
+            created_by: user_id,
+            updated_by: user_id,
+        })
+        .await
+    }
+
+    async fn update_price_list(&self, id: Uuid, request: PriceListRequest) -> Result<PriceList> {
+        let current = self.get_price_list(id).await?;
+        self.save(PriceList {
+            name: request.name,
+            currency: request.currency,
+            channel: request.channel,
+            customer_group: request.customer_group,
+            priority: request.priority,
+            valid_from: request.valid_from,
+            valid_until: request.valid_until,
+            is_active: request.is_active,
+            updated_at: Utc::now(),
+            updated_by: self.tenant_context.user_id,
+            ..current
+        })
+        .await
+    }
+
+    async fn delete_price_list(&self, id: Uuid) -> Result<()> {
+        if !self.repository.delete_price_list(self.tenant_context.tenant_id, id).await? {
+            return Err(Error::new(ErrorCode::NotFound, format!("Price list {} not found", id)));
+        }
+        Ok(())
+    }
+
+    async fn import_lines_csv(&self, id: Uuid, content: &str) -> Result<PriceLineImportResult> {
+        let list = self.get_price_list(id).await?;
+        let ParsedPriceLines { rows, mut errors } = parse_price_lines(content, &list.currency);
+
+        let mut skus: Vec<String> = rows.iter().map(|row| row.sku.clone()).collect();
+        skus.sort();
+        skus.dedup();
+        let targets: HashMap<(String, Option<String>), PriceTarget> = self
+            .repository
+            .find_price_targets(self.tenant_context.tenant_id, &skus)
+            .await?
+            .into_iter()
+            .map(|target| ((target.sku.clone(), target.variant_sku.clone()), target))
+            .collect();
+
+        let mut lines = Vec::with_capacity(rows.len());
+        for row in rows {
+            match targets.get(&(row.sku.clone(), row.variant_sku.clone())) {
+                Some(target) => lines.push(PriceListLine {
+                    price_list_id: list.id,
+                    product_id: target.product_id,
+                    variant_id: target.variant_id,
+                    pricing: row.pricing,
+                }),
+                None => errors.push(PriceLineError {
+                    line_number: row.line_number,
+                    message: match &row.variant_sku {
+                        Some(variant_sku) => format!("Unknown variant {} of product {}", variant_sku, row.sku),
+                        None => format!("Unknown product {}", row.sku),
+                    },
+                }),
+            }
+        }
+        errors.sort_by_key(|e| e.line_number);
+
+        if !lines.is_empty() {
+            self.repository
+                .upsert_price_list_lines(self.tenant_context.tenant_id, list.id, &lines)
+                .await?;
+        }
+
+        Ok(PriceLineImportResult {
+            price_list_id: list.id,
+            lines_loaded: lines.len(),
+            errors,
+        })
+    }
+
+    async fn effective_price(&self, product_id: Uuid, variant_id: Option<Uuid>, context: &PriceContext) -> Result<EffectivePrice> {
+        let tenant_id = self.tenant_context.tenant_id;
+        let base = self
+            .repository
+            .price_base(tenant_id, product_id, variant_id)
+            .await?
+            .ok_or_else(|| Error::new(ErrorCode::NotFound, "Product not found"))?;
+
+        let lists = self.repository.list_price_lists(tenant_id).await?;
+        let lines = self.repository.lines_for_product(tenant_id, product_id).await?;
+        resolve_price(&base, &lists, &lines, context)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct MemoryRepository {
+        lists: Mutex<Vec<PriceList>>,
+        targets: Vec<PriceTarget>,
+        lines: Mutex<Vec<PriceListLine>>,
+    }
+
+    #[async_trait]
+    impl PriceListRepository for MemoryRepository {
+        async fn list_price_lists(&self, _tenant_id: Uuid) -> Result<Vec<PriceList>> {
+            Ok(self.lists.lock().unwrap().clone())
+        }
+
+        async fn get_price_list(&self, _tenant_id: Uuid, id: Uuid) -> Result<Option<PriceList>> {
+            Ok(self.lists.lock().unwrap().iter().find(|l| l.id == id).cloned())
+        }
+
+        async fn save_price_list(&self, list: &PriceList) -> Result<()> {
+            let mut lists = self.lists.lock().unwrap();
+            lists.retain(|l| l.id != list.id);
+            lists.push(list.clone());
+            Ok(())
+        }
+
+        async fn delete_price_list(&self, _tenant_id: Uuid, id: Uuid) -> Result<bool> {
+            let mut lists = self.lists.lock().unwrap();
+            let before = lists.len();
+            lists.retain(|l| l.id != id);
+            Ok(lists.len() < before)
+        }
+
+        async fn lines_for_product(&self, _tenant_id: Uuid, product_id: Uuid) -> Result<Vec<PriceListLine>> {
+            Ok(self.lines.lock().unwrap().iter().filter(|l| l.product_id == product_id).cloned().collect())
+        }
+
+        async fn price_base(&self, _tenant_id: Uuid, product_id: Uuid, variant_id: Option<Uuid>) -> Result<Option<PriceBase>> {
+            Ok(Some(PriceBase {
+                product_id,
+                variant_id,
+                base_price: 10_000,
+                variant_adjustment: 0,
+                currency: "EUR".to_string(),
+            }))
+        }
+
+        async fn find_price_targets(&self, _tenant_id: Uuid, skus: &[String]) -> Result<Vec<PriceTarget>> {
+            Ok(self.targets.iter().filter(|t| skus.contains(&t.sku)).cloned().collect())
+        }
+
+        async fn upsert_price_list_lines(&self, _tenant_id: Uuid, _price_list_id: Uuid, lines: &[PriceListLine]) -> Result<()> {
+            let mut stored = self.lines.lock().unwrap();
+            for line in lines {
+                stored.retain(|l| {
+                    (l.price_list_id, l.product_id, l.variant_id) != (line.price_list_id, line.product_id, line.variant_id)
+                });
+                stored.push(line.clone());
+            }
+            Ok(())
+        }
+    }
+
+    fn service(repository: &Arc<MemoryRepository>) -> DefaultPriceListService {
+        let context = TenantContext::new(Uuid::new_v4(), "acme".to_string(), Uuid::new_v4());
+        DefaultPriceListService::new(repository.clone(), context)
+    }
+
+    fn request(name: &str, priority: i32, group: Option<&str>) -> PriceListRequest {
+        PriceListRequest {
+            name: name.to_string(),
+            currency: "eur".to_string(),
+            channel: None,
+            customer_group: group.map(str::to_string),
+            priority,
+            valid_from: None,
+            valid_until: None,
+            is_active: true,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_save_rejects_tie_with_existing_list() {
+        let repository = Arc::new(MemoryRepository::default());
+        let service = service(&repository);
+
+        let wholesale = service.create_price_list(request("Wholesale", 10, Some(" Wholesale "))).await.unwrap();
+        assert_eq!((wholesale.currency.as_str(), wholesale.customer_group.as_deref()), ("EUR", Some("wholesale")));
+
+        let tie = service.create_price_list(request("Wholesale 2", 10, Some("wholesale"))).await.unwrap_err();
+        assert_eq!(tie.code, ErrorCode::ConflictError);
+
+        // Updating a list never conflicts with itself
+        let renamed = service.update_price_list(wholesale.id, request("Trade", 10, Some("wholesale"))).await.unwrap();
+        assert_eq!((renamed.id, renamed.name.as_str()), (wholesale.id, "Trade"));
+
+        service.create_price_list(request("Retail", 10, Some("retail"))).await.unwrap();
+        assert_eq!(repository.lists.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_csv_import_resolves_skus_and_prices_through_list() {
+        let (product, variant) = (Uuid::new_v4(), Uuid::new_v4());
+        let repository = Arc::new(MemoryRepository {
+            targets: vec![
+                PriceTarget { sku: "SHIRT".to_string(), variant_sku: None, product_id: product, variant_id: None },
+                PriceTarget {
+                    sku: "SHIRT".to_string(),
+                    variant_sku: Some("SHIRT-RED".to_string()),
+                    product_id: product,
+                    variant_id: Some(variant),
+                },
+            ],
+            ..Default::default()
+        });
+        let service = service(&repository);
+        let list = service.create_price_list(request("Wholesale", 10, Some("wholesale"))).await.unwrap();
+
+        let result = service
+            .import_lines_csv(list.id, "SHIRT,,80.00,,\nSHIRT,SHIRT-RED,,10,EUR\nMUG,,5,,\nSHIRT,SHIRT-BLUE,,10,\n")
+            .await
+            .unwrap();
+        assert_eq!(result.lines_loaded, 2);
+        let failed: Vec<usize> = result.errors.iter().map(|e| e.line_number).collect();
+        assert_eq!(failed, vec![3, 4]);
+
+        let context = PriceContext {
+            customer_tier: None,
+            quantity: None,
+            location: None,
+            date_time: Utc::now(),
+            customer_group: Some("wholesale".to_string()),
+            channel: None,
+            currency: None,
+        };
+        assert_eq!(service.effective_price(product, None, &context).await.unwrap().final_price, 8_000);
+        assert_eq!(service.effective_price(product, Some(variant), &context).await.unwrap().final_price, 9_000);
+
+        let retail = PriceContext { customer_group: None, ..context };
+        assert_eq!(service.effective_price(product, None, &retail).await.unwrap().final_price, 10_000);
+    }
+}
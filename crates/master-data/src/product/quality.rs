@@ -0,0 +1,479 @@
+//! # Quality Inspections
+//!
+//! Inspections are scheduled on a calendar, by hand or by two rules:
+//!
+//! - **Incoming goods**: every purchase order receipt books its units into a
+//!   batch; a batch of a product flagged `inspection_required` gets an
+//!   inspection due [`InspectionRules::incoming_due_days`] after it arrived
+//! - **Certification expiry**: once a product's certification expires within
+//!   [`InspectionRules::schedule_ahead_days`], it gets a re-inspection due
+//!   [`InspectionRules::reinspect_days_before_expiry`] before the expiry
+//!
+//! The rules run on receipt and in a daily job. A rule schedules at most one
+//! inspection per reference (the batch, or the expiry date), so running them
+//! again never duplicates one.
+//!
+//! Recording the result of a batch's inspection sets the batch's quality
+//! status. A failed (or recalled) batch holds its units back from sellable
+//! availability until it is dispositioned: released, reworked, scrapped or
+//! returned to the supplier. Scrapped and returned units leave the stock
+//! through the usual adjustments and supplier returns.
+
+use super::model::QualityStatus;
+use chrono::{DateTime, Duration, Utc};
+use erp_core::error::{Error, ErrorCode, Result};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+pub const RULE_INCOMING_GOODS: &str = "incoming_goods";
+pub const RULE_CERTIFICATION_EXPIRY: &str = "certification_expiry";
+
+pub const INCOMING_INSPECTION: &str = "incoming";
+pub const RECERTIFICATION_INSPECTION: &str = "recertification";
+
+/// Needed to record inspection results and disposition batches
+pub const QUALITY_INSPECTION_PERMISSION: &str = "products:quality_inspect";
+
+/// Most urgent and least urgent priority
+pub const PRIORITY_RANGE: (i32, i32) = (1, 5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InspectionStatus {
+    Scheduled,
+    Passed,
+    Failed,
+}
+
+impl InspectionStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            InspectionStatus::Scheduled => "scheduled",
+            InspectionStatus::Passed => "passed",
+            InspectionStatus::Failed => "failed",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "scheduled" => Some(InspectionStatus::Scheduled),
+            "passed" => Some(InspectionStatus::Passed),
+            "failed" => Some(InspectionStatus::Failed),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InspectionOutcome {
+    Passed,
+    Failed,
+}
+
+impl InspectionOutcome {
+    pub fn status(self) -> InspectionStatus {
+        match self {
+            InspectionOutcome::Passed => InspectionStatus::Passed,
+            InspectionOutcome::Failed => InspectionStatus::Failed,
+        }
+    }
+
+    /// Quality status of the inspected batch
+    pub fn batch_status(self) -> QualityStatus {
+        match self {
+            InspectionOutcome::Passed => QualityStatus::Passed,
+            InspectionOutcome::Failed => QualityStatus::Failed,
+        }
+    }
+}
+
+/// What happens to a failed or recalled batch
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchDisposition {
+    /// Sold as is, under concession
+    Release,
+    Rework,
+    Scrap,
+    ReturnToSupplier,
+}
+
+impl BatchDisposition {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BatchDisposition::Release => "release",
+            BatchDisposition::Rework => "rework",
+            BatchDisposition::Scrap => "scrap",
+            BatchDisposition::ReturnToSupplier => "return_to_supplier",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "release" => Some(BatchDisposition::Release),
+            "rework" => Some(BatchDisposition::Rework),
+            "scrap" => Some(BatchDisposition::Scrap),
+            "return_to_supplier" => Some(BatchDisposition::ReturnToSupplier),
+            _ => None,
+        }
+    }
+}
+
+/// Result of an inspection, stored as its result payload
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InspectionResult {
+    pub outcome: InspectionOutcome,
+    #[serde(default)]
+    pub findings: Vec<String>,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QualityInspection {
+    pub id: Uuid,
+    pub tenant_id: Uuid,
+    pub product_id: Uuid,
+    pub batch_id: Option<Uuid>,
+    pub inspection_type: String,
+    pub scheduled_date: DateTime<Utc>,
+    pub inspector_id: Option<Uuid>,
+    pub status: InspectionStatus,
+    /// 1 is the most urgent
+    pub priority: i32,
+    pub result: Option<InspectionResult>,
+    /// Rule that scheduled the inspection; `None` if scheduled by hand
+    pub rule: Option<String>,
+    pub reference: Option<String>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub completed_by: Option<Uuid>,
+    pub created_by: Uuid,
+    pub created_at: DateTime<Utc>,
+}
+
+impl QualityInspection {
+    pub fn is_overdue(&self, now: DateTime<Utc>) -> bool {
+        self.status == InspectionStatus::Scheduled && self.scheduled_date < now
+    }
+}
+
+/// An inspection to put on the calendar
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewInspection {
+    pub product_id: Uuid,
+    pub batch_id: Option<Uuid>,
+    pub inspection_type: String,
+    pub scheduled_date: DateTime<Utc>,
+    pub inspector_id: Option<Uuid>,
+    pub priority: i32,
+    pub rule: Option<String>,
+    pub reference: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduleInspectionRequest {
+    pub product_id: Uuid,
+    pub batch_id: Option<Uuid>,
+    pub inspection_type: String,
+    pub scheduled_date: DateTime<Utc>,
+    pub inspector_id: Option<Uuid>,
+    pub priority: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RescheduleInspectionRequest {
+    pub scheduled_date: DateTime<Utc>,
+    /// Keeps the assigned inspector if not given
+    pub inspector_id: Option<Uuid>,
+    /// Keeps the priority if not given
+    pub priority: Option<i32>,
+}
+
+/// Units of a product received together at a location
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QualityBatch {
+    pub id: Uuid,
+    pub tenant_id: Uuid,
+    pub product_id: Uuid,
+    pub location_id: Uuid,
+    pub batch_number: String,
+    pub quantity: i32,
+    pub quality_status: QualityStatus,
+    pub purchase_order_id: Option<Uuid>,
+    pub disposition: Option<BatchDisposition>,
+    pub dispositioned_at: Option<DateTime<Utc>>,
+    pub dispositioned_by: Option<Uuid>,
+    pub received_at: DateTime<Utc>,
+}
+
+impl QualityBatch {
+    /// Whether the batch's units are held back from sale
+    pub fn blocks_stock(&self) -> bool {
+        matches!(self.quality_status, QualityStatus::Failed | QualityStatus::Recalled) && self.disposition.is_none()
+    }
+}
+
+/// A received batch of an inspection-required product without incoming inspection
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchCandidate {
+    pub batch_id: Uuid,
+    pub product_id: Uuid,
+    pub received_at: DateTime<Utc>,
+}
+
+/// A product whose certification expires soon, or has expired
+#[derive(Debug, Clone, PartialEq)]
+pub struct CertificationCandidate {
+    pub product_id: Uuid,
+    pub certification_expiry: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InspectionRules {
+    pub incoming_due_days: i64,
+    pub incoming_priority: i32,
+    pub reinspect_days_before_expiry: i64,
+    pub schedule_ahead_days: i64,
+    pub reinspection_priority: i32,
+}
+
+impl Default for InspectionRules {
+    fn default() -> Self {
+        Self {
+            incoming_due_days: 2,
+            incoming_priority: 2,
+            reinspect_days_before_expiry: 14,
+            schedule_ahead_days: 60,
+            reinspection_priority: 3,
+        }
+    }
+}
+
+impl InspectionRules {
+    /// Certifications expiring before this are due for re-inspection
+    pub fn certification_horizon(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        now + Duration::days(self.schedule_ahead_days)
+    }
+}
+
+/// Incoming-goods inspections of the received batches
+pub fn incoming_inspections(candidates: &[BatchCandidate], rules: &InspectionRules) -> Vec<NewInspection> {
+    candidates
+        .iter()
+        .map(|candidate| NewInspection {
+            product_id: candidate.product_id,
+            batch_id: Some(candidate.batch_id),
+            inspection_type: INCOMING_INSPECTION.to_string(),
+            scheduled_date: candidate.received_at + Duration::days(rules.incoming_due_days),
+            inspector_id: None,
+            priority: rules.incoming_priority,
+            rule: Some(RULE_INCOMING_GOODS.to_string()),
+            reference: Some(candidate.batch_id.to_string()),
+        })
+        .collect()
+}
+
+/// Re-inspections of the certifications expiring within the horizon; one
+/// whose date has passed already is due right away
+pub fn reinspections(candidates: &[CertificationCandidate], rules: &InspectionRules, now: DateTime<Utc>) -> Vec<NewInspection> {
+    candidates
+        .iter()
+        .filter(|candidate| candidate.certification_expiry <= rules.certification_horizon(now))
+        .map(|candidate| NewInspection {
+            product_id: candidate.product_id,
+            batch_id: None,
+            inspection_type: RECERTIFICATION_INSPECTION.to_string(),
+            scheduled_date: (candidate.certification_expiry - Duration::days(rules.reinspect_days_before_expiry)).max(now),
+            inspector_id: None,
+            priority: rules.reinspection_priority,
+            rule: Some(RULE_CERTIFICATION_EXPIRY.to_string()),
+            reference: Some(candidate.certification_expiry.date_naive().to_string()),
+        })
+        .collect()
+}
+
+fn validate_priority(priority: i32) -> Result<()> {
+    let (most, least) = PRIORITY_RANGE;
+    if !(most..=least).contains(&priority) {
+        return Err(Error::new(
+            ErrorCode::ValidationFailed,
+            format!("Priority must be between {} and {}", most, least),
+        ));
+    }
+    Ok(())
+}
+
+/// The inspection a request schedules by hand
+pub fn scheduled_inspection(request: &ScheduleInspectionRequest) -> Result<NewInspection> {
+    let inspection_type = request.inspection_type.trim();
+    if inspection_type.is_empty() {
+        return Err(Error::new(ErrorCode::ValidationFailed, "Inspection type is required"));
+    }
+    let priority = request.priority.unwrap_or(3);
+    validate_priority(priority)?;
+
+    Ok(NewInspection {
+        product_id: request.product_id,
+        batch_id: request.batch_id,
+        inspection_type: inspection_type.to_string(),
+        scheduled_date: request.scheduled_date,
+        inspector_id: request.inspector_id,
+        priority,
+        rule: None,
+        reference: None,
+    })
+}
+
+/// Only open inspections can be rescheduled or get a result
+pub fn check_open(inspection: &QualityInspection) -> Result<()> {
+    if inspection.status != InspectionStatus::Scheduled {
+        return Err(Error::new(
+            ErrorCode::ConflictError,
+            format!("Inspection {} is already {}", inspection.id, inspection.status.as_str()),
+        ));
+    }
+    Ok(())
+}
+
+pub fn validate_reschedule(inspection: &QualityInspection, request: &RescheduleInspectionRequest) -> Result<()> {
+    check_open(inspection)?;
+    if let Some(priority) = request.priority {
+        validate_priority(priority)?;
+    }
+    Ok(())
+}
+
+/// A failed inspection has to say what was found
+pub fn validate_result(inspection: &QualityInspection, result: &InspectionResult) -> Result<()> {
+    check_open(inspection)?;
+    if result.outcome == InspectionOutcome::Failed && result.findings.iter().all(|finding| finding.trim().is_empty()) {
+        return Err(Error::new(ErrorCode::ValidationFailed, "A failed inspection needs at least one finding"));
+    }
+    Ok(())
+}
+
+/// Only a batch holding its stock back can be dispositioned, once
+pub fn validate_disposition(batch: &QualityBatch) -> Result<()> {
+    if !batch.blocks_stock() {
+        return Err(Error::new(
+            ErrorCode::ConflictError,
+            format!("Batch {} is {} and needs no disposition", batch.batch_number, batch.quality_status.as_str()),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 3, 2, 6, 0, 0).unwrap()
+    }
+
+    fn inspection(status: InspectionStatus) -> QualityInspection {
+        QualityInspection {
+            id: Uuid::new_v4(),
+            tenant_id: Uuid::new_v4(),
+            product_id: Uuid::new_v4(),
+            batch_id: Some(Uuid::new_v4()),
+            inspection_type: INCOMING_INSPECTION.to_string(),
+            scheduled_date: now(),
+            inspector_id: None,
+            status,
+            priority: 2,
+            result: None,
+            rule: None,
+            reference: None,
+            completed_at: None,
+            completed_by: None,
+            created_by: Uuid::nil(),
+            created_at: now(),
+        }
+    }
+
+    #[test]
+    fn test_incoming_inspection_per_batch() {
+        let batch = BatchCandidate { batch_id: Uuid::new_v4(), product_id: Uuid::new_v4(), received_at: now() };
+        let inspections = incoming_inspections(std::slice::from_ref(&batch), &InspectionRules::default());
+
+        assert_eq!(inspections.len(), 1);
+        assert_eq!(inspections[0].batch_id, Some(batch.batch_id));
+        assert_eq!(inspections[0].scheduled_date, now() + Duration::days(2));
+        assert_eq!(inspections[0].rule.as_deref(), Some(RULE_INCOMING_GOODS));
+        assert_eq!(inspections[0].reference, Some(batch.batch_id.to_string()));
+    }
+
+    #[test]
+    fn test_reinspection_ahead_of_certification_expiry() {
+        let rules = InspectionRules::default();
+        let soon = CertificationCandidate { product_id: Uuid::new_v4(), certification_expiry: now() + Duration::days(30) };
+        let expired = CertificationCandidate { product_id: Uuid::new_v4(), certification_expiry: now() - Duration::days(1) };
+        let later = CertificationCandidate { product_id: Uuid::new_v4(), certification_expiry: now() + Duration::days(90) };
+
+        let inspections = reinspections(&[soon.clone(), expired.clone(), later], &rules, now());
+        assert_eq!(inspections.len(), 2);
+        assert_eq!(inspections[0].product_id, soon.product_id);
+        assert_eq!(inspections[0].scheduled_date, now() + Duration::days(16));
+        assert_eq!(inspections[0].reference.as_deref(), Some("2026-04-01"));
+        // Overdue already: due right away
+        assert_eq!(inspections[1].product_id, expired.product_id);
+        assert_eq!(inspections[1].scheduled_date, now());
+    }
+
+    #[test]
+    fn test_failed_result_needs_findings() {
+        let open = inspection(InspectionStatus::Scheduled);
+        let failed = InspectionResult { outcome: InspectionOutcome::Failed, findings: vec![" ".to_string()], notes: None };
+        assert_eq!(validate_result(&open, &failed).unwrap_err().code, ErrorCode::ValidationFailed);
+
+        let failed = InspectionResult { findings: vec!["Seal broken".to_string()], ..failed };
+        assert!(validate_result(&open, &failed).is_ok());
+
+        let done = inspection(InspectionStatus::Passed);
+        assert_eq!(validate_result(&done, &failed).unwrap_err().code, ErrorCode::ConflictError);
+    }
+
+    #[test]
+    fn test_failed_batch_blocks_until_dispositioned() {
+        let mut batch = QualityBatch {
+            id: Uuid::new_v4(),
+            tenant_id: Uuid::new_v4(),
+            product_id: Uuid::new_v4(),
+            location_id: Uuid::new_v4(),
+            batch_number: "PO-1001".to_string(),
+            quantity: 40,
+            quality_status: InspectionOutcome::Failed.batch_status(),
+            purchase_order_id: None,
+            disposition: None,
+            dispositioned_at: None,
+            dispositioned_by: None,
+            received_at: now(),
+        };
+        assert!(batch.blocks_stock());
+        assert!(validate_disposition(&batch).is_ok());
+
+        batch.disposition = Some(BatchDisposition::Scrap);
+        assert!(!batch.blocks_stock());
+        assert_eq!(validate_disposition(&batch).unwrap_err().code, ErrorCode::ConflictError);
+
+        batch.disposition = None;
+        batch.quality_status = QualityStatus::Passed;
+        assert!(!batch.blocks_stock());
+    }
+
+    #[test]
+    fn test_priority_is_checked() {
+        let request = ScheduleInspectionRequest {
+            product_id: Uuid::new_v4(),
+            batch_id: None,
+            inspection_type: "audit".to_string(),
+            scheduled_date: now(),
+            inspector_id: None,
+            priority: Some(9),
+        };
+        assert!(scheduled_inspection(&request).is_err());
+        assert_eq!(scheduled_inspection(&ScheduleInspectionRequest { priority: None, ..request }).unwrap().priority, 3);
+    }
+}
@@ -0,0 +1,541 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use erp_core::error::{Error, ErrorCode, Result};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::product::model::{Product, ProductAttributes};
+use crate::product::quality::*;
+use crate::product::repository::QualityInspectionRepository;
+use crate::product::service::{ComplianceStatus, ComplianceViolation, QualityEngine, RecallResult};
+use crate::types::TenantContext;
+
+/// What one evaluation of the scheduling rules put on the calendar
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InspectionRun {
+    pub incoming: usize,
+    pub reinspections: usize,
+}
+
+/// The inspection calendar of one tenant
+#[async_trait]
+pub trait QualityInspectionService: Send + Sync {
+    async fn schedule(&self, request: ScheduleInspectionRequest) -> Result<QualityInspection>;
+
+    async fn get_inspection(&self, inspection_id: Uuid) -> Result<QualityInspection>;
+
+    /// Open inspections due from `now` until `days` ahead
+    async fn upcoming(&self, now: DateTime<Utc>, days: i64) -> Result<Vec<QualityInspection>>;
+
+    /// Open inspections whose date has passed
+    async fn overdue(&self, now: DateTime<Utc>) -> Result<Vec<QualityInspection>>;
+
+    /// Record pass or fail; the inspected batch takes the outcome as its quality status
+    async fn record_result(&self, inspection_id: Uuid, result: InspectionResult) -> Result<QualityInspection>;
+
+    async fn reschedule(&self, inspection_id: Uuid, request: RescheduleInspectionRequest) -> Result<QualityInspection>;
+
+    /// Decide on a failed or recalled batch, which releases its hold on the stock
+    async fn disposition_batch(&self, batch_id: Uuid, disposition: BatchDisposition) -> Result<QualityBatch>;
+
+    /// Schedule the incoming-goods inspections of a purchase order's receipt
+    async fn goods_received(&self, purchase_order_id: Uuid) -> Result<Vec<QualityInspection>>;
+
+    /// Evaluate both scheduling rules over all of the tenant's batches and products
+    async fn run_rules(&self, now: DateTime<Utc>) -> Result<InspectionRun>;
+}
+
+pub struct DefaultQualityInspectionService {
+    repository: Arc<dyn QualityInspectionRepository>,
+    tenant_context: TenantContext,
+    rules: InspectionRules,
+}
+
+impl DefaultQualityInspectionService {
+    pub fn new(repository: Arc<dyn QualityInspectionRepository>, tenant_context: TenantContext, rules: InspectionRules) -> Self {
+        Self {
+            repository,
+            tenant_context,
+            rules,
+        }
+    }
+
+    fn require_permission(&self, action: &str) -> Result<()> {
+        if !self.tenant_context.has_permission(QUALITY_INSPECTION_PERMISSION) {
+            return Err(Error::new(
+                ErrorCode::PermissionDenied,
+                format!("{} requires {}", action, QUALITY_INSPECTION_PERMISSION),
+            ));
+        }
+        Ok(())
+    }
+
+    async fn schedule_incoming(&self, purchase_order_id: Option<Uuid>) -> Result<Vec<QualityInspection>> {
+        let tenant_id = self.tenant_context.tenant_id;
+        let candidates = self.repository.incoming_candidates(tenant_id, purchase_order_id).await?;
+        if candidates.is_empty() {
+            return Ok(Vec::new());
+        }
+        self.repository
+            .schedule_inspections(
+                tenant_id,
+                &incoming_inspections(&candidates, &self.rules),
+                self.tenant_context.user_id,
+                Utc::now(),
+            )
+            .await
+    }
+}
+
+#[async_trait]
+impl QualityInspectionService for DefaultQualityInspectionService {
+    async fn schedule(&self, request: ScheduleInspectionRequest) -> Result<QualityInspection> {
+        let inspection = scheduled_inspection(&request)?;
+        self.repository
+            .schedule_inspections(self.tenant_context.tenant_id, &[inspection], self.tenant_context.user_id, Utc::now())
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| Error::new(ErrorCode::NotFound, "Product or batch not found"))
+    }
+
+    async fn get_inspection(&self, inspection_id: Uuid) -> Result<QualityInspection> {
+        self.repository
+            .get_inspection(self.tenant_context.tenant_id, inspection_id)
+            .await?
+            .ok_or_else(|| Error::new(ErrorCode::NotFound, "Inspection not found"))
+    }
+
+    async fn upcoming(&self, now: DateTime<Utc>, days: i64) -> Result<Vec<QualityInspection>> {
+        let inspections = self
+            .repository
+            .open_inspections(self.tenant_context.tenant_id, now + Duration::days(days.max(0)))
+            .await?;
+        Ok(inspections.into_iter().filter(|inspection| !inspection.is_overdue(now)).collect())
+    }
+
+    async fn overdue(&self, now: DateTime<Utc>) -> Result<Vec<QualityInspection>> {
+        self.repository.open_inspections(self.tenant_context.tenant_id, now).await
+    }
+
+    async fn record_result(&self, inspection_id: Uuid, result: InspectionResult) -> Result<QualityInspection> {
+        self.require_permission("Recording inspection results")?;
+        let inspection = self.get_inspection(inspection_id).await?;
+        validate_result(&inspection, &result)?;
+
+        self.repository
+            .record_result(self.tenant_context.tenant_id, inspection_id, &result, self.tenant_context.user_id, Utc::now())
+            .await
+    }
+
+    async fn reschedule(&self, inspection_id: Uuid, request: RescheduleInspectionRequest) -> Result<QualityInspection> {
+        let inspection = self.get_inspection(inspection_id).await?;
+        validate_reschedule(&inspection, &request)?;
+
+        self.repository
+            .reschedule(self.tenant_context.tenant_id, inspection_id, &request, Utc::now())
+            .await
+    }
+
+    async fn disposition_batch(&self, batch_id: Uuid, disposition: BatchDisposition) -> Result<QualityBatch> {
+        self.require_permission("Dispositioning batches")?;
+        let tenant_id = self.tenant_context.tenant_id;
+        let batch = self
+            .repository
+            .get_batch(tenant_id, batch_id)
+            .await?
+            .ok_or_else(|| Error::new(ErrorCode::NotFound, "Batch not found"))?;
+        validate_disposition(&batch)?;
+
+        self.repository
+            .disposition_batch(tenant_id, batch_id, disposition, self.tenant_context.user_id, Utc::now())
+            .await
+    }
+
+    async fn goods_received(&self, purchase_order_id: Uuid) -> Result<Vec<QualityInspection>> {
+        self.schedule_incoming(Some(purchase_order_id)).await
+    }
+
+    async fn run_rules(&self, now: DateTime<Utc>) -> Result<InspectionRun> {
+        let tenant_id = self.tenant_context.tenant_id;
+        let incoming = self.schedule_incoming(None).await?;
+
+        let candidates = self
+            .repository
+            .certification_candidates(tenant_id, self.rules.certification_horizon(now))
+            .await?;
+        let reinspections = reinspections(&candidates, &self.rules, now);
+        let scheduled = if reinspections.is_empty() {
+            Vec::new()
+        } else {
+            self.repository
+                .schedule_inspections(tenant_id, &reinspections, self.tenant_context.user_id, now)
+                .await?
+        };
+
+        Ok(InspectionRun {
+            incoming: incoming.len(),
+            reinspections: scheduled.len(),
+        })
+    }
+}
+
+/// [`QualityEngine`] of the product service, backed by the inspection calendar
+pub struct RepositoryQualityEngine {
+    repository: Arc<dyn QualityInspectionRepository>,
+    tenant_context: TenantContext,
+    rules: InspectionRules,
+}
+
+impl RepositoryQualityEngine {
+    pub fn new(repository: Arc<dyn QualityInspectionRepository>, tenant_context: TenantContext, rules: InspectionRules) -> Self {
+        Self {
+            repository,
+            tenant_context,
+            rules,
+        }
+    }
+}
+
+#[async_trait]
+impl QualityEngine for RepositoryQualityEngine {
+    /// Compliance as far as the certification expiry tells
+    async fn check_product_compliance(&self, _product: &Product, attributes: Option<&ProductAttributes>) -> Result<ComplianceStatus> {
+        let now = Utc::now();
+        let mut violations = Vec::new();
+        let mut recommendations = Vec::new();
+        if let Some(expiry) = attributes.and_then(|attributes| attributes.certification_expiry) {
+            if expiry <= now {
+                violations.push(ComplianceViolation {
+                    violation_type: "certification_expired".to_string(),
+                    severity: "high".to_string(),
+                    description: format!("Certification expired on {}", expiry.date_naive()),
+                    detected_date: now,
+                    resolution_required: true,
+                });
+            } else if expiry <= now + Duration::days(self.rules.reinspect_days_before_expiry) {
+                recommendations.push(format!("Re-inspect before the certification expires on {}", expiry.date_naive()));
+            }
+        }
+
+        Ok(ComplianceStatus {
+            is_compliant: violations.is_empty(),
+            compliance_score: if violations.is_empty() { 1.0 } else { 0.0 },
+            certifications: Vec::new(),
+            violations,
+            recommendations,
+        })
+    }
+
+    /// Put an inspection on the calendar, due like an incoming-goods inspection
+    async fn schedule_inspection(&self, product_id: Uuid, inspection_type: &str, user_id: Uuid) -> Result<QualityInspection> {
+        let now = Utc::now();
+        let inspection = scheduled_inspection(&ScheduleInspectionRequest {
+            product_id,
+            batch_id: None,
+            inspection_type: inspection_type.to_string(),
+            scheduled_date: now + Duration::days(self.rules.incoming_due_days),
+            inspector_id: None,
+            priority: None,
+        })?;
+        self.repository
+            .schedule_inspections(self.tenant_context.tenant_id, &[inspection], user_id, now)
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| Error::new(ErrorCode::NotFound, "Product not found"))
+    }
+
+    /// Hold back the products' batches in stock until they are dispositioned
+    async fn initiate_recall(&self, product_ids: Vec<Uuid>, _reason: &str, _user_id: Uuid) -> Result<RecallResult> {
+        let batches = self
+            .repository
+            .recall_batches(self.tenant_context.tenant_id, &product_ids, Utc::now())
+            .await?;
+        Ok(RecallResult {
+            recall_id: Uuid::new_v4(),
+            affected_products: product_ids,
+            batches_affected: batches,
+            customers_notified: 0,
+            recall_status: "initiated".to_string(),
+            estimated_cost: 0,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::product::model::QualityStatus;
+    use std::sync::Mutex;
+
+    /// Batches and the calendar in memory
+    #[derive(Default)]
+    struct MemoryInspections {
+        /// Batches with their purchase order, and whether their product needs inspection
+        batches: Mutex<Vec<(QualityBatch, bool)>>,
+        certifications: Vec<CertificationCandidate>,
+        inspections: Mutex<Vec<QualityInspection>>,
+    }
+
+    impl MemoryInspections {
+        fn batch(&self, batch_id: Uuid) -> QualityBatch {
+            self.batches.lock().unwrap().iter().find(|(b, _)| b.id == batch_id).unwrap().0.clone()
+        }
+    }
+
+    #[async_trait]
+    impl QualityInspectionRepository for MemoryInspections {
+        async fn schedule_inspections(
+            &self,
+            tenant_id: Uuid,
+            inspections: &[NewInspection],
+            created_by: Uuid,
+            created_at: DateTime<Utc>,
+        ) -> Result<Vec<QualityInspection>> {
+            let mut stored = self.inspections.lock().unwrap();
+            let mut scheduled = Vec::new();
+            for new in inspections {
+                let duplicate = new.rule.is_some()
+                    && stored.iter().any(|i| i.product_id == new.product_id && i.rule == new.rule && i.reference == new.reference);
+                if duplicate {
+                    continue;
+                }
+                let inspection = QualityInspection {
+                    id: Uuid::new_v4(),
+                    tenant_id,
+                    product_id: new.product_id,
+                    batch_id: new.batch_id,
+                    inspection_type: new.inspection_type.clone(),
+                    scheduled_date: new.scheduled_date,
+                    inspector_id: new.inspector_id,
+                    status: InspectionStatus::Scheduled,
+                    priority: new.priority,
+                    result: None,
+                    rule: new.rule.clone(),
+                    reference: new.reference.clone(),
+                    completed_at: None,
+                    completed_by: None,
+                    created_by,
+                    created_at,
+                };
+                stored.push(inspection.clone());
+                scheduled.push(inspection);
+            }
+            Ok(scheduled)
+        }
+
+        async fn get_inspection(&self, _tenant_id: Uuid, inspection_id: Uuid) -> Result<Option<QualityInspection>> {
+            Ok(self.inspections.lock().unwrap().iter().find(|i| i.id == inspection_id).cloned())
+        }
+
+        async fn open_inspections(&self, _tenant_id: Uuid, until: DateTime<Utc>) -> Result<Vec<QualityInspection>> {
+            let inspections = self.inspections.lock().unwrap();
+            Ok(inspections
+                .iter()
+                .filter(|i| i.status == InspectionStatus::Scheduled && i.scheduled_date < until)
+                .cloned()
+                .collect())
+        }
+
+        async fn record_result(
+            &self,
+            _tenant_id: Uuid,
+            inspection_id: Uuid,
+            result: &InspectionResult,
+            completed_by: Uuid,
+            completed_at: DateTime<Utc>,
+        ) -> Result<QualityInspection> {
+            let mut inspections = self.inspections.lock().unwrap();
+            let inspection = inspections.iter_mut().find(|i| i.id == inspection_id).unwrap();
+            inspection.status = result.outcome.status();
+            inspection.result = Some(result.clone());
+            inspection.completed_at = Some(completed_at);
+            inspection.completed_by = Some(completed_by);
+            if let Some(batch_id) = inspection.batch_id {
+                let mut batches = self.batches.lock().unwrap();
+                let (batch, _) = batches.iter_mut().find(|(b, _)| b.id == batch_id).unwrap();
+                batch.quality_status = result.outcome.batch_status();
+                batch.disposition = None;
+            }
+            Ok(inspection.clone())
+        }
+
+        async fn reschedule(
+            &self,
+            _tenant_id: Uuid,
+            inspection_id: Uuid,
+            request: &RescheduleInspectionRequest,
+            _updated_at: DateTime<Utc>,
+        ) -> Result<QualityInspection> {
+            let mut inspections = self.inspections.lock().unwrap();
+            let inspection = inspections.iter_mut().find(|i| i.id == inspection_id).unwrap();
+            inspection.scheduled_date = request.scheduled_date;
+            inspection.inspector_id = request.inspector_id.or(inspection.inspector_id);
+            inspection.priority = request.priority.unwrap_or(inspection.priority);
+            Ok(inspection.clone())
+        }
+
+        async fn get_batch(&self, _tenant_id: Uuid, batch_id: Uuid) -> Result<Option<QualityBatch>> {
+            Ok(self.batches.lock().unwrap().iter().find(|(b, _)| b.id == batch_id).map(|(b, _)| b.clone()))
+        }
+
+        async fn disposition_batch(
+            &self,
+            _tenant_id: Uuid,
+            batch_id: Uuid,
+            disposition: BatchDisposition,
+            dispositioned_by: Uuid,
+            dispositioned_at: DateTime<Utc>,
+        ) -> Result<QualityBatch> {
+            let mut batches = self.batches.lock().unwrap();
+            let (batch, _) = batches.iter_mut().find(|(b, _)| b.id == batch_id).unwrap();
+            batch.disposition = Some(disposition);
+            batch.dispositioned_by = Some(dispositioned_by);
+            batch.dispositioned_at = Some(dispositioned_at);
+            Ok(batch.clone())
+        }
+
+        async fn incoming_candidates(&self, _tenant_id: Uuid, purchase_order_id: Option<Uuid>) -> Result<Vec<BatchCandidate>> {
+            let inspections = self.inspections.lock().unwrap();
+            Ok(self
+                .batches
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|(batch, required)| {
+                    *required
+                        && batch.quality_status == QualityStatus::Pending
+                        && purchase_order_id.is_none_or(|id| batch.purchase_order_id == Some(id))
+                        && !inspections.iter().any(|i| i.batch_id == Some(batch.id))
+                })
+                .map(|(batch, _)| BatchCandidate {
+                    batch_id: batch.id,
+                    product_id: batch.product_id,
+                    received_at: batch.received_at,
+                })
+                .collect())
+        }
+
+        async fn certification_candidates(&self, _tenant_id: Uuid, until: DateTime<Utc>) -> Result<Vec<CertificationCandidate>> {
+            Ok(self.certifications.iter().filter(|c| c.certification_expiry <= until).cloned().collect())
+        }
+
+        async fn recall_batches(&self, _tenant_id: Uuid, _product_ids: &[Uuid], _recalled_at: DateTime<Utc>) -> Result<Vec<String>> {
+            Ok(Vec::new())
+        }
+
+        async fn unalerted_overdue_inspections(&self, _now: DateTime<Utc>) -> Result<Vec<QualityInspection>> {
+            Ok(Vec::new())
+        }
+
+        async fn mark_overdue_alerted(&self, _tenant_id: Uuid, _inspection_id: Uuid, _alerted_at: DateTime<Utc>) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    fn received_batch(tenant_id: Uuid, purchase_order_id: Uuid) -> QualityBatch {
+        QualityBatch {
+            id: Uuid::new_v4(),
+            tenant_id,
+            product_id: Uuid::new_v4(),
+            location_id: Uuid::new_v4(),
+            batch_number: "PO-2001".to_string(),
+            quantity: 24,
+            quality_status: QualityStatus::Pending,
+            purchase_order_id: Some(purchase_order_id),
+            disposition: None,
+            dispositioned_at: None,
+            dispositioned_by: None,
+            received_at: Utc::now(),
+        }
+    }
+
+    fn service(repository: &Arc<MemoryInspections>, tenant_id: Uuid, permissions: &[&str]) -> DefaultQualityInspectionService {
+        let mut context = TenantContext::new(tenant_id, "tenant_test".to_string(), Uuid::new_v4());
+        context.permissions = permissions.iter().map(|p| p.to_string()).collect();
+        DefaultQualityInspectionService::new(repository.clone(), context, InspectionRules::default())
+    }
+
+    #[tokio::test]
+    async fn test_receipt_schedules_incoming_inspection_of_required_products() {
+        let tenant_id = Uuid::new_v4();
+        let purchase_order_id = Uuid::new_v4();
+        let required = received_batch(tenant_id, purchase_order_id);
+        let not_required = received_batch(tenant_id, purchase_order_id);
+        let other_order = received_batch(tenant_id, Uuid::new_v4());
+        let repository = Arc::new(MemoryInspections {
+            batches: Mutex::new(vec![(required.clone(), true), (not_required, false), (other_order.clone(), true)]),
+            ..Default::default()
+        });
+        let service = service(&repository, tenant_id, &[]);
+
+        let scheduled = service.goods_received(purchase_order_id).await.unwrap();
+        assert_eq!(scheduled.len(), 1);
+        assert_eq!(scheduled[0].batch_id, Some(required.id));
+        assert_eq!(scheduled[0].rule.as_deref(), Some(RULE_INCOMING_GOODS));
+        assert_eq!(scheduled[0].scheduled_date, required.received_at + Duration::days(2));
+
+        // Receiving again does not schedule twice; the job picks up the other order's batch
+        assert!(service.goods_received(purchase_order_id).await.unwrap().is_empty());
+        let run = service.run_rules(Utc::now()).await.unwrap();
+        assert_eq!(run, InspectionRun { incoming: 1, reinspections: 0 });
+        assert_eq!(repository.inspections.lock().unwrap()[1].batch_id, Some(other_order.id));
+    }
+
+    #[tokio::test]
+    async fn test_job_schedules_reinspection_once_per_expiry() {
+        let tenant_id = Uuid::new_v4();
+        let now = Utc::now();
+        let repository = Arc::new(MemoryInspections {
+            certifications: vec![CertificationCandidate {
+                product_id: Uuid::new_v4(),
+                certification_expiry: now + Duration::days(20),
+            }],
+            ..Default::default()
+        });
+        let service = service(&repository, tenant_id, &[]);
+
+        assert_eq!(service.run_rules(now).await.unwrap().reinspections, 1);
+        assert_eq!(service.run_rules(now).await.unwrap().reinspections, 0);
+        let upcoming = service.upcoming(now, 30).await.unwrap();
+        assert_eq!(upcoming.len(), 1);
+        assert_eq!(upcoming[0].scheduled_date, now + Duration::days(6));
+        assert!(service.overdue(now).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_failed_result_flips_batch_status_until_dispositioned() {
+        let tenant_id = Uuid::new_v4();
+        let purchase_order_id = Uuid::new_v4();
+        let batch = received_batch(tenant_id, purchase_order_id);
+        let repository = Arc::new(MemoryInspections {
+            batches: Mutex::new(vec![(batch.clone(), true)]),
+            ..Default::default()
+        });
+        let inspection = service(&repository, tenant_id, &[]).goods_received(purchase_order_id).await.unwrap().remove(0);
+        let failed = InspectionResult {
+            outcome: InspectionOutcome::Failed,
+            findings: vec!["Moisture in 3 of 24 cartons".to_string()],
+            notes: None,
+        };
+
+        let denied = service(&repository, tenant_id, &[]).record_result(inspection.id, failed.clone()).await.unwrap_err();
+        assert_eq!(denied.code, ErrorCode::PermissionDenied);
+
+        let inspector = service(&repository, tenant_id, &[QUALITY_INSPECTION_PERMISSION]);
+        let recorded = inspector.record_result(inspection.id, failed.clone()).await.unwrap();
+        assert_eq!(recorded.status, InspectionStatus::Failed);
+        assert_eq!(repository.batch(batch.id).quality_status, QualityStatus::Failed);
+        assert!(repository.batch(batch.id).blocks_stock());
+
+        let again = inspector.record_result(inspection.id, failed).await.unwrap_err();
+        assert_eq!(again.code, ErrorCode::ConflictError);
+
+        let released = inspector.disposition_batch(batch.id, BatchDisposition::ReturnToSupplier).await.unwrap();
+        assert!(!released.blocks_stock());
+        let twice = inspector.disposition_batch(batch.id, BatchDisposition::Scrap).await.unwrap_err();
+        assert_eq!(twice.code, ErrorCode::ConflictError);
+    }
+}
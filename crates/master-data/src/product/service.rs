@@ -5,17 +5,39 @@
 
 use super::{
     model::*,
-    repository::{ProductRepository, BulkPriceUpdateRequest, PriceContext, AdvancedProductSearch as RepoAdvancedSearch},
-    analytics::ProductAnalyticsEngine
+    repository::{ProductRepository, ProductGraph, BulkPriceUpdateRequest, PriceContext, AdvancedProductSearch as RepoAdvancedSearch},
+    analytics::ProductAnalyticsEngine,
+    barcode::{analyze, ValidateBarcodeRequest},
+    barcode_service::{require_available, require_valid, BarcodeService},
+    bulk_pricing::{BulkPriceUpdateOutcome, BulkPriceUpdateStatus},
+    bulk_pricing_service::BulkPriceUpdateService,
+    enhancement::ProductEnhancementJob,
+    feed::{self, ProductFeed, ProductFeedFormat},
+    integrity::{initial_stage, RULE_PRODUCT_CREATED},
+    lifecycle::{recommendation, StageChange, StageChangeSource},
+    lifecycle_service::LifecycleAutomationService,
+    price_list_service::PriceListService,
+    quality::QualityInspection,
+    repository::ProductSuggestionRepository,
+    suggestion::{triage, SuggestionSource},
 };
+use crate::currency::conversion::convert_amount;
+use crate::currency::ExchangeRateProvider;
+use crate::spans;
+use crate::supplier::{CatalogQuoteRequest, SupplierCatalogService};
 use crate::types::{TenantContext, PaginationOptions, PaginationResult};
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
 use erp_core::error::{Error, ErrorCode, Result};
+use erp_core::jobs::{types::QueuedJob, JobQueue};
+use erp_core::latency::LatencyBudget;
+use erp_core::plan_limits::{PlanLimitGuard, PlanResource};
+use erp_core::{Warning, WarningCode};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use uuid::Uuid;
 use std::collections::HashMap;
+use tracing::Instrument;
 
 /// Comprehensive Product Service trait with all advanced features
 #[async_trait]
@@ -74,6 +96,10 @@ pub trait ProductService: Send + Sync {
     async fn get_lifecycle_recommendations(&self, product_id: Uuid) -> Result<Vec<LifecycleRecommendation>>;
 
     // === AI-Powered Features ===
+    /// The AI engine's category, reorder point, description and SEO texts for
+    /// a new product, and its optimization suggestions; run as a job after
+    /// the product is created
+    async fn enhance_product(&self, product_id: Uuid) -> Result<Product>;
     async fn generate_product_description(&self, product_id: Uuid, style: &str) -> Result<String>;
     async fn optimize_seo_content(&self, product_id: Uuid) -> Result<SeoOptimization>;
     async fn detect_anomalies(&self, product_id: Uuid) -> Result<Vec<ProductAnomaly>>;
@@ -89,7 +115,8 @@ pub trait ProductService: Send + Sync {
 
     // === Integration & Automation ===
     async fn sync_with_external_system(&self, system_id: &str, product_mapping: ExternalProductMapping) -> Result<SyncResult>;
-    async fn export_product_feed(&self, format: &str, filter: Option<AdvancedProductSearch>) -> Result<String>;
+    /// The catalog as a `json`, `csv` or `xlsx` file, narrowed by `filter` if given
+    async fn export_product_feed(&self, format: &str, filter: Option<AdvancedProductSearch>) -> Result<ProductFeed>;
     async fn schedule_automated_tasks(&self, product_id: Uuid, tasks: Vec<AutomatedTask>) -> Result<Vec<TaskSchedule>>;
     async fn validate_product_data(&self, product_data: &CreateProductRequest) -> Result<ValidationResult>;
 
@@ -108,6 +135,16 @@ pub struct DefaultProductService {
     ai_engine: Arc<dyn AIEngine>,
     pricing_engine: Arc<dyn PricingEngine>,
     quality_engine: Arc<dyn QualityEngine>,
+    supplier_catalog: Option<Arc<dyn SupplierCatalogService>>,
+    exchange_rates: Option<Arc<dyn ExchangeRateProvider>>,
+    bulk_price_updates: Arc<dyn BulkPriceUpdateService>,
+    barcodes: Option<Arc<dyn BarcodeService>>,
+    price_lists: Option<Arc<dyn PriceListService>>,
+    suggestions: Option<Arc<dyn ProductSuggestionRepository>>,
+    lifecycle: Option<Arc<dyn LifecycleAutomationService>>,
+    plan_limits: Option<Arc<dyn PlanLimitGuard>>,
+    enhancements: Option<Arc<dyn JobQueue>>,
+    latency: LatencyBudget,
 }
 
 impl DefaultProductService {
@@ -118,6 +155,7 @@ impl DefaultProductService {
         ai_engine: Arc<dyn AIEngine>,
         pricing_engine: Arc<dyn PricingEngine>,
         quality_engine: Arc<dyn QualityEngine>,
+        bulk_price_updates: Arc<dyn BulkPriceUpdateService>,
     ) -> Self {
         Self {
             repository,
@@ -126,9 +164,167 @@ impl DefaultProductService {
             ai_engine,
             pricing_engine,
             quality_engine,
+            supplier_catalog: None,
+            exchange_rates: None,
+            bulk_price_updates,
+            barcodes: None,
+            price_lists: None,
+            suggestions: None,
+            lifecycle: None,
+            plan_limits: None,
+            enhancements: None,
+            latency: LatencyBudget::default(),
         }
     }
 
+    /// Cost products from supplier catalogs instead of the flat cost price
+    pub fn with_supplier_catalog(mut self, supplier_catalog: Arc<dyn SupplierCatalogService>) -> Self {
+        self.supplier_catalog = Some(supplier_catalog);
+        self
+    }
+
+    /// Convert catalog prices quoted in another currency into the product's
+    pub fn with_exchange_rates(mut self, exchange_rates: Arc<dyn ExchangeRateProvider>) -> Self {
+        self.exchange_rates = Some(exchange_rates);
+        self
+    }
+
+    /// `amount` of `from` in `to`, at today's rate
+    async fn convert_price(&self, amount: i64, from: &str, to: &str) -> Result<i64> {
+        if from.eq_ignore_ascii_case(to) {
+            return Ok(amount);
+        }
+        let rate = match &self.exchange_rates {
+            Some(rates) => rates.rate(self.tenant_context.tenant_id, from, to, Utc::now().date_naive())
+                .await
+                .map_err(|e| Error::new(ErrorCode::InternalServerError, e.to_string()))?,
+            None => None,
+        };
+        rate.map(|rate| convert_amount(amount, rate.rate))
+            .ok_or_else(|| Error::new(ErrorCode::ValidationFailed, format!("No exchange rate from {} to {}", from, to)))
+    }
+
+    /// Store barcodes through the barcode service, which keeps them unique across
+    /// the tenant's products and variants
+    pub fn with_barcodes(mut self, barcodes: Arc<dyn BarcodeService>) -> Self {
+        self.barcodes = Some(barcodes);
+        self
+    }
+
+    /// Resolve effective prices through the customer group and channel price lists
+    pub fn with_price_lists(mut self, price_lists: Arc<dyn PriceListService>) -> Self {
+        self.price_lists = Some(price_lists);
+        self
+    }
+
+    /// Store the AI engine's optimization suggestions for review. Without this
+    /// they only end up in the analytics record and nothing is applied.
+    pub fn with_suggestions(mut self, suggestions: Arc<dyn ProductSuggestionRepository>) -> Self {
+        self.suggestions = Some(suggestions);
+        self
+    }
+
+    /// Evaluate and change lifecycle stages through the lifecycle automation,
+    /// whose rules then give the lifecycle recommendations
+    pub fn with_lifecycle(mut self, lifecycle: Arc<dyn LifecycleAutomationService>) -> Self {
+        self.lifecycle = Some(lifecycle);
+        self
+    }
+
+    /// Refuse new products once the tenant is at the product limit of its plan
+    pub fn with_plan_limits(mut self, plan_limits: Arc<dyn PlanLimitGuard>) -> Self {
+        self.plan_limits = Some(plan_limits);
+        self
+    }
+
+    /// Queue the AI enhancement of new products on `queue` once they are
+    /// stored. Without this new products are not enhanced.
+    pub fn with_enhancement_queue(mut self, queue: Arc<dyn JobQueue>) -> Self {
+        self.enhancements = Some(queue);
+        self
+    }
+
+    /// Time methods against these budgets instead of the default ones
+    pub fn with_latency_budget(mut self, latency: LatencyBudget) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    /// Queue the enhancement of a stored product; failing to is logged, the
+    /// product stays
+    async fn queue_enhancement(&self, product_id: Uuid) {
+        let Some(queue) = &self.enhancements else {
+            return;
+        };
+        let job = ProductEnhancementJob {
+            tenant_id: self.tenant_context.tenant_id,
+            product_id,
+            requested_by: self.tenant_context.user_id,
+        };
+        let queued = match QueuedJob::new(&job) {
+            Ok(queued) => queue.enqueue(queued).await,
+            Err(e) => Err(e.into()),
+        };
+        if let Err(e) = queued {
+            tracing::warn!("Enhancement of product {} not queued: {}", product_id, e);
+        }
+    }
+
+    /// Record the suggestions made for a just saved product, first applying
+    /// those the tenant opted to auto-apply
+    async fn record_suggestions(
+        &self,
+        mut product: Product,
+        optimization_suggestions: &[OptimizationSuggestion],
+        source: SuggestionSource,
+    ) -> Result<Product> {
+        let Some(suggestions) = &self.suggestions else {
+            return Ok(product);
+        };
+        if optimization_suggestions.is_empty() {
+            return Ok(product);
+        }
+
+        let tenant_id = self.tenant_context.tenant_id;
+        let rules = suggestions.auto_apply_rules(tenant_id).await?;
+        let now = Utc::now();
+        let mut records = triage(&mut product, optimization_suggestions, &rules, source, self.tenant_context.user_id, now);
+        if records.iter().any(|record| record.decided_at.is_some()) {
+            product.updated_at = now;
+            product.updated_by = self.tenant_context.user_id;
+            product = self.repository.update_product(&product).instrument(spans::repository("update_product")).await?;
+        }
+
+        for record in &mut records {
+            record.product_updated_at = product.updated_at;
+        }
+        suggestions.record_suggestions(tenant_id, product.id, &records).await?;
+        Ok(product)
+    }
+
+    /// Normalized barcode, or `None` for a blank one. Without the barcode service
+    /// only the format and check digit are verified.
+    async fn check_barcode(&self, barcode: &str, product_id: Option<Uuid>) -> Result<Option<String>> {
+        if barcode.trim().is_empty() {
+            return Ok(None);
+        }
+        let normalized = match &self.barcodes {
+            Some(barcodes) => {
+                let validation = barcodes
+                    .validate_barcode(ValidateBarcodeRequest {
+                        barcode: barcode.to_string(),
+                        symbology: None,
+                        product_id,
+                        variant_id: None,
+                    })
+                    .await?;
+                require_available(&validation)?
+            }
+            None => require_valid(&analyze(barcode, None, Utc::now().date_naive()))?,
+        };
+        Ok(Some(normalized))
+    }
+
     /// Comprehensive product validation with AI-enhanced checks
     async fn validate_product_creation(&self, request: &CreateProductRequest) -> Result<()> {
         // Basic validation
@@ -141,7 +337,9 @@ impl DefaultProductService {
         }
 
         // SKU uniqueness check
-        if let Some(_existing) = self.repository.get_product_by_sku(self.tenant_context.tenant_id, &request.sku).await? {
+        if let Some(_existing) = self.repository.get_product_by_sku(self.tenant_context.tenant_id, &request.sku)
+            .instrument(spans::repository("get_product_by_sku"))
+            .await? {
             return Err(Error::new(ErrorCode::ConflictError, "SKU already exists"));
         }
 
@@ -176,19 +374,36 @@ impl DefaultProductService {
             }
         }
 
+        if request.is_serialized && !request.is_tracked {
+            return Err(Error::new(ErrorCode::ValidationFailed, "Serialized products must be inventory tracked"));
+        }
+
         // Category validation
         if let Some(category_id) = request.category_id {
             // Verify category exists (simplified check)
-            let hierarchy = self.repository.get_category_hierarchy(self.tenant_context.tenant_id).await?;
+            let hierarchy = self.repository.get_category_hierarchy(self.tenant_context.tenant_id)
+                .instrument(spans::repository("get_category_hierarchy"))
+                .await?;
             if !hierarchy.iter().any(|c| c.id == category_id) {
                 return Err(Error::new(ErrorCode::ValidationFailed, "Invalid category ID"));
             }
         }
 
-        // AI-powered validation
-        let ai_validation = self.ai_engine.validate_product_data(request).await?;
-        if !ai_validation.is_valid {
-            return Err(Error::new(ErrorCode::ValidationFailed, format!("AI validation failed: {}", ai_validation.reason)));
+        // AI-powered validation; when the engine is unavailable the product is saved without it
+        match self.ai_engine.validate_product_data(request)
+            .instrument(spans::engine("ai", "validate_product_data"))
+            .await {
+            Ok(ai_validation) if !ai_validation.is_valid => {
+                return Err(Error::new(ErrorCode::ValidationFailed, format!("AI validation failed: {}", ai_validation.reason)));
+            }
+            Ok(_) => {}
+            Err(e) => {
+                tracing::warn!("AI validation of product {} skipped: {}", request.sku, e);
+                erp_core::warnings::warn(Warning::new(
+                    WarningCode::AiValidationSkipped,
+                    "AI validation was unavailable; the product data was not checked by it",
+                ));
+            }
         }
 
         Ok(())
@@ -214,7 +429,9 @@ impl DefaultProductService {
 
     /// AI-powered auto-categorization
     async fn determine_category(&self, product: &Product) -> Result<Option<Uuid>> {
-        let suggestions = self.ai_engine.suggest_categories(product).await?;
+        let suggestions = self.ai_engine.suggest_categories(product)
+            .instrument(spans::engine("ai", "suggest_categories"))
+            .await?;
 
         if let Some(suggestion) = suggestions.first() {
             if suggestion.confidence > 0.8 {
@@ -231,7 +448,9 @@ impl DefaultProductService {
             return Ok(None);
         }
 
-        let demand_forecast = self.ai_engine.forecast_demand(product.id, 30).await?;
+        let demand_forecast = self.ai_engine.forecast_demand(product.id, 30)
+            .instrument(spans::engine("ai", "forecast_demand"))
+            .await?;
         let lead_time = product.lead_time_days.unwrap_or(7);
         let safety_factor = 1.5; // 50% safety margin
 
@@ -241,16 +460,22 @@ impl DefaultProductService {
 
     /// Intelligent pricing suggestions
     async fn suggest_optimal_pricing(&self, product: &Product) -> Result<PricingSuggestion> {
-        let market_analysis = self.pricing_engine.analyze_market_pricing(product).await?;
-        let cost_analysis = self.pricing_engine.calculate_cost_structure(product).await?;
-        let competition_analysis = self.pricing_engine.analyze_competition(product).await?;
+        let market_analysis = self.pricing_engine.analyze_market_pricing(product)
+            .instrument(spans::engine("pricing", "analyze_market_pricing"))
+            .await?;
+        let cost_analysis = self.pricing_engine.calculate_cost_structure(product)
+            .instrument(spans::engine("pricing", "calculate_cost_structure"))
+            .await?;
+        let competition_analysis = self.pricing_engine.analyze_competition(product)
+            .instrument(spans::engine("pricing", "analyze_competition"))
+            .await?;
 
         let suggested_price = self.pricing_engine.optimize_price(
             product,
             &market_analysis,
             &cost_analysis,
             &competition_analysis,
-        ).await?;
+        ).instrument(spans::engine("pricing", "optimize_price")).await?;
 
         Ok(PricingSuggestion {
             suggested_base_price: suggested_price.base_price,
@@ -265,9 +490,18 @@ impl DefaultProductService {
 
 #[async_trait]
 impl ProductService for DefaultProductService {
+    #[tracing::instrument(skip_all, fields(tenant_id = %self.tenant_context.tenant_id))]
     async fn create_product(&self, request: CreateProductRequest) -> Result<Product> {
+        let _latency = self.latency.start("product.write", "create_product");
         // Comprehensive validation
         self.validate_product_creation(&request).await?;
+        if let Some(plan_limits) = &self.plan_limits {
+            plan_limits.ensure_room(self.tenant_context.tenant_id, PlanResource::Products).await?;
+        }
+        let barcode = match request.barcode.as_deref() {
+            Some(barcode) => self.check_barcode(barcode, None).await?,
+            None => None,
+        };
 
         // Create product with intelligent defaults
         let mut product = Product::new(
@@ -286,32 +520,22 @@ impl ProductService for DefaultProductService {
         product.currency = request.currency;
         product.cost_price = request.cost_price;
         product.is_tracked = request.is_tracked;
+        product.is_serialized = request.is_serialized;
         product.current_stock = request.current_stock;
         product.min_stock_level = request.min_stock_level;
         product.reorder_point = request.reorder_point;
         product.primary_supplier_id = request.primary_supplier_id;
         product.weight = request.weight;
-        product.barcode = request.barcode;
+        product.barcode = barcode;
         product.brand = request.brand;
         product.manufacturer = request.manufacturer;
         product.tags = request.tags;
 
-        // AI-powered enhancements
-        if product.category_id.is_none() {
-            product.category_id = self.determine_category(&product).await?;
-        }
-
-        if product.reorder_point.is_none() {
-            product.reorder_point = self.calculate_intelligent_reorder_point(&product).await?;
-        }
-
-        // Create the product
-        let created_product = self.repository.create_product(&product).await?;
-
         // Create enhanced attributes
+        let now = Utc::now();
         let attributes = ProductAttributes {
             id: Uuid::new_v4(),
-            product_id: created_product.id,
+            product_id: product.id,
             tenant_id: self.tenant_context.tenant_id,
             digital_twin_id: None,
             iot_device_ids: None,
@@ -325,6 +549,7 @@ impl ProductService for DefaultProductService {
             quality_grade: None,
             certification_expiry: None,
             batch_tracking_required: false,
+            inspection_required: request.inspection_required,
             shelf_life_days: None,
             storage_temperature_min: None,
             storage_temperature_max: None,
@@ -340,37 +565,19 @@ impl ProductService for DefaultProductService {
             blockchain_hash: None,
             authenticity_token: None,
             provenance_data: None,
-            created_at: Utc::now(),
-            updated_at: Utc::now(),
+            created_at: now,
+            updated_at: now,
             created_by: self.tenant_context.user_id,
             updated_by: self.tenant_context.user_id,
         };
 
-        let _attributes = self.repository.create_product_attributes(&attributes).await?;
-
-        // Generate AI-enhanced content
-        let ai_description = self.ai_engine.generate_description(&created_product).await?;
-        let seo_optimization = self.ai_engine.optimize_seo(&created_product).await?;
-
-        // Update with AI-generated content
-        let mut updated_product = created_product.clone();
-        if ai_description.quality_score > 0.7 {
-            updated_product.description = Some(ai_description.content);
-        }
-        updated_product.meta_title = Some(seo_optimization.title);
-        updated_product.meta_description = Some(seo_optimization.description);
-        updated_product.updated_at = Utc::now();
-        updated_product.updated_by = self.tenant_context.user_id;
-
-        let final_product = self.repository.update_product(&updated_product).await?;
-
-        // Create initial analytics record
+        // Initial analytics record
         let analytics = ProductAnalytics {
             id: Uuid::new_v4(),
-            product_id: final_product.id,
+            product_id: product.id,
             tenant_id: self.tenant_context.tenant_id,
-            period_start: Utc::now(),
-            period_end: Utc::now(),
+            period_start: now,
+            period_end: now,
             period_type: "daily".to_string(),
             units_sold: 0,
             revenue: 0,
@@ -391,208 +598,90 @@ impl ProductService for DefaultProductService {
             anomaly_score: None,
             predicted_demand: None,
             optimization_suggestions: None,
-            calculated_at: Utc::now(),
-            created_at: Utc::now(),
-            updated_at: Utc::now(),
+            calculated_at: now,
+            created_at: now,
+            updated_at: now,
         };
 
-        let _analytics = self.repository.create_analytics_record(&analytics).await?;
-
-        // Create lifecycle record
-        let lifecycle = ProductLifecycle {
-            id: Uuid::new_v4(),
-            product_id: final_product.id,
-            tenant_id: self.tenant_context.tenant_id,
-            stage: LifecycleStage::Development,
-            stage_changed_at: Utc::now(),
-            previous_stage: None,
-            time_to_market: None,
-            development_cost: None,
-            total_revenue: 0,
-            units_produced: 0,
-            units_sold: 0,
-            planned_eol_date: None,
-            replacement_product_id: None,
-            sunset_strategy: None,
-            total_carbon_footprint: None,
-            materials_recycled: None,
-            waste_generated: None,
-            notes: None,
-            created_at: Utc::now(),
-            updated_at: Utc::now(),
-            created_by: self.tenant_context.user_id,
-            updated_by: self.tenant_context.user_id,
+        let lifecycle = StageChange {
+            product_id: product.id,
+            to: initial_stage(&product.status),
+            source: StageChangeSource::Automation,
+            rule: Some(RULE_PRODUCT_CREATED.to_string()),
+            evidence: None,
+            changed_by: self.tenant_context.user_id,
+            changed_at: now,
         };
 
-        let _lifecycle = self.repository.create_lifecycle_record(&lifecycle).await?;
+        // The product and its rows are stored together or not at all
+        let created_product = self.repository.create_product_graph(&ProductGraph {
+            product,
+            attributes,
+            analytics,
+            lifecycle,
+        }).instrument(spans::repository("create_product_graph")).await?;
+        if let (Some(barcodes), Some(barcode)) = (&self.barcodes, &created_product.barcode) {
+            barcodes.assign_barcode(created_product.id, None, Some(barcode.clone())).await?;
+        }
+
+        // AI enhancements run after the product is stored
+        self.queue_enhancement(created_product.id).await;
 
-        Ok(final_product)
+        Ok(created_product)
     }
 
+    #[tracing::instrument(skip_all, fields(tenant_id = %self.tenant_context.tenant_id, product_id = %product_id))]
     async fn get_product(&self, product_id: Uuid) -> Result<Option<Product>> {
-        self.repository.get_product_by_id(self.tenant_context.tenant_id, product_id).await
+        let _latency = self.latency.start("product.read", "get_product");
+        self.repository.get_product_by_id(self.tenant_context.tenant_id, product_id)
+            .instrument(spans::repository("get_product_by_id"))
+            .await
     }
 
+    #[tracing::instrument(skip_all, fields(tenant_id = %self.tenant_context.tenant_id))]
     async fn get_product_by_sku(&self, sku: &str) -> Result<Option<Product>> {
-        self.repository.get_product_by_sku(self.tenant_context.tenant_id, sku).await
+        let _latency = self.latency.start("product.read", "get_product_by_sku");
+        self.repository.get_product_by_sku(self.tenant_context.tenant_id, sku)
+            .instrument(spans::repository("get_product_by_sku"))
+            .await
     }
 
-    async fn update_product(&self, product_id: Uuid, request: UpdateProductRequest) -> Result<Product> {
+    #[tracing::instrument(skip_all, fields(tenant_id = %self.tenant_context.tenant_id, product_id = %product_id))]
+    async fn update_product(&self, product_id: Uuid, mut request: UpdateProductRequest) -> Result<Product> {
+        let _latency = self.latency.start("product.write", "update_product");
         // Get existing product
-        let mut product = self.repository.get_product_by_id(self.tenant_context.tenant_id, product_id).await?
+        let mut product = self.repository.get_product_by_id(self.tenant_context.tenant_id, product_id)
+            .instrument(spans::repository("get_product_by_id"))
+            .await?
             .ok_or_else(|| Error::new(ErrorCode::NotFound, "Product not found"))?;
 
         // Update fields if provided
-        if let Some(name) = request.name {
-            if name.trim().is_empty() {
-                return Err(Error::new(ErrorCode::ValidationFailed, "Product name cannot be empty"));
-            }
-            product.name = name;
-        }
-
-        if let Some(description) = request.description {
-            product.description = Some(description);
-        }
-
-        if let Some(category_id) = request.category_id {
-            product.category_id = Some(category_id);
-        }
+        let barcode = request.barcode.take();
+        product.apply_update(request)?;
 
-        if let Some(product_type) = request.product_type {
-            product.product_type = product_type;
-        }
-
-        if let Some(status) = request.status {
-            product.status = status;
-        }
-
-        if let Some(unit_of_measure) = request.unit_of_measure {
-            product.unit_of_measure = unit_of_measure;
-        }
-
-        if let Some(base_price) = request.base_price {
-            if base_price < 0 {
-                return Err(Error::new(ErrorCode::ValidationFailed, "Base price cannot be negative"));
-            }
-            product.base_price = base_price;
-        }
-
-        if let Some(cost_price) = request.cost_price {
-            if cost_price < 0 {
-                return Err(Error::new(ErrorCode::ValidationFailed, "Cost price cannot be negative"));
-            }
-            product.cost_price = Some(cost_price);
-        }
-
-        if let Some(list_price) = request.list_price {
-            if list_price < 0 {
-                return Err(Error::new(ErrorCode::ValidationFailed, "List price cannot be negative"));
-            }
-            product.list_price = Some(list_price);
-        }
-
-        if let Some(is_tracked) = request.is_tracked {
-            product.is_tracked = is_tracked;
-        }
-
-        if let Some(current_stock) = request.current_stock {
-            if current_stock < 0 {
-                return Err(Error::new(ErrorCode::ValidationFailed, "Stock level cannot be negative"));
-            }
-            product.current_stock = Some(current_stock);
-        }
-
-        if let Some(min_stock_level) = request.min_stock_level {
-            product.min_stock_level = Some(min_stock_level);
-        }
-
-        if let Some(max_stock_level) = request.max_stock_level {
-            product.max_stock_level = Some(max_stock_level);
-        }
-
-        if let Some(reorder_point) = request.reorder_point {
-            product.reorder_point = Some(reorder_point);
-        }
-
-        if let Some(primary_supplier_id) = request.primary_supplier_id {
-            product.primary_supplier_id = Some(primary_supplier_id);
-        }
-
-        if let Some(weight) = request.weight {
-            product.weight = Some(weight);
-        }
-
-        if let Some(dimensions_length) = request.dimensions_length {
-            product.dimensions_length = Some(dimensions_length);
-        }
-
-        if let Some(dimensions_width) = request.dimensions_width {
-            product.dimensions_width = Some(dimensions_width);
-        }
-
-        if let Some(dimensions_height) = request.dimensions_height {
-            product.dimensions_height = Some(dimensions_height);
-        }
-
-        if let Some(barcode) = request.barcode {
-            product.barcode = Some(barcode);
-        }
-
-        if let Some(brand) = request.brand {
-            product.brand = Some(brand);
-        }
-
-        if let Some(manufacturer) = request.manufacturer {
-            product.manufacturer = Some(manufacturer);
-        }
-
-        if let Some(model_number) = request.model_number {
-            product.model_number = Some(model_number);
-        }
-
-        if let Some(warranty_months) = request.warranty_months {
-            product.warranty_months = Some(warranty_months);
-        }
-
-        if let Some(is_featured) = request.is_featured {
-            product.is_featured = is_featured;
-        }
-
-        if let Some(tags) = request.tags {
-            product.tags = Some(tags);
-        }
-
-        if let Some(notes) = request.notes {
-            product.notes = Some(notes);
+        let barcode_changed = barcode.is_some();
+        if let Some(barcode) = barcode {
+            product.barcode = self.check_barcode(&barcode, Some(product_id)).await?;
         }
 
         // Update metadata
         product.updated_at = Utc::now();
         product.updated_by = self.tenant_context.user_id;
 
-        // AI-powered optimizations on update
-        let optimization_suggestions = self.ai_engine.suggest_optimizations(&product).await?;
-
-        // Apply high-confidence suggestions automatically
-        for suggestion in &optimization_suggestions {
-            if suggestion.confidence > 0.9 {
-                match suggestion.suggestion_type.as_str() {
-                    "price_optimization" => {
-                        if let Some(suggested_price) = suggestion.suggested_value.as_i64() {
-                            product.base_price = suggested_price;
-                        }
-                    }
-                    "reorder_point_optimization" => {
-                        if let Some(suggested_reorder) = suggestion.suggested_value.as_i64() {
-                            product.reorder_point = Some(suggested_reorder as i32);
-                        }
-                    }
-                    _ => {} // Handle other optimization types
-                }
-            }
+        let updated_product = self.repository.update_product(&product)
+            .instrument(spans::repository("update_product"))
+            .await?;
+        if let (Some(barcodes), true) = (&self.barcodes, barcode_changed) {
+            barcodes.assign_barcode(product_id, None, product.barcode.clone()).await?;
         }
 
-        let updated_product = self.repository.update_product(&product).await?;
+        // AI-powered optimizations are stored for review; only opted-in types are applied
+        let optimization_suggestions = self.ai_engine.suggest_optimizations(&updated_product)
+            .instrument(spans::engine("ai", "suggest_optimizations"))
+            .await?;
+        let updated_product = self
+            .record_suggestions(updated_product, &optimization_suggestions, SuggestionSource::Update)
+            .await?;
 
         // Update analytics with change tracking
         let analytics_update = ProductAnalytics {
@@ -626,14 +715,20 @@ impl ProductService for DefaultProductService {
             updated_at: Utc::now(),
         };
 
-        let _analytics = self.repository.create_analytics_record(&analytics_update).await?;
+        let _analytics = self.repository.create_analytics_record(&analytics_update)
+            .instrument(spans::repository("create_analytics_record"))
+            .await?;
 
         Ok(updated_product)
     }
 
+    #[tracing::instrument(skip_all, fields(tenant_id = %self.tenant_context.tenant_id, product_id = %product_id))]
     async fn delete_product(&self, product_id: Uuid) -> Result<()> {
+        let _latency = self.latency.start("product.write", "delete_product");
         // Get product to check if it can be deleted
-        let product = self.repository.get_product_by_id(self.tenant_context.tenant_id, product_id).await?
+        let product = self.repository.get_product_by_id(self.tenant_context.tenant_id, product_id)
+            .instrument(spans::repository("get_product_by_id"))
+            .await?
             .ok_or_else(|| Error::new(ErrorCode::NotFound, "Product not found"))?;
 
         // Business rules for deletion
@@ -646,15 +741,21 @@ impl ProductService for DefaultProductService {
         }
 
         // Check for dependencies (simplified check)
-        let analytics = self.repository.get_product_analytics(self.tenant_context.tenant_id, product_id, "all").await?;
+        let analytics = self.repository.get_product_analytics(self.tenant_context.tenant_id, product_id, "all")
+            .instrument(spans::repository("get_product_analytics"))
+            .await?;
         if analytics.iter().any(|a| a.units_sold > 0) {
             return Err(Error::new(ErrorCode::BusinessRuleViolation, "Cannot delete product with sales history. Consider archiving instead."));
         }
 
-        self.repository.delete_product(self.tenant_context.tenant_id, product_id).await
+        self.repository.delete_product(self.tenant_context.tenant_id, product_id)
+            .instrument(spans::repository("delete_product"))
+            .await
     }
 
+    #[tracing::instrument(skip_all, fields(tenant_id = %self.tenant_context.tenant_id, product_id = %product_id))]
     async fn activate_product(&self, product_id: Uuid) -> Result<Product> {
+        let _latency = self.latency.start("product.write", "activate_product");
         let request = UpdateProductRequest {
             status: Some(ProductStatus::Active),
             ..Default::default()
@@ -662,7 +763,9 @@ impl ProductService for DefaultProductService {
         self.update_product(product_id, request).await
     }
 
+    #[tracing::instrument(skip_all, fields(tenant_id = %self.tenant_context.tenant_id, product_id = %product_id))]
     async fn deactivate_product(&self, product_id: Uuid) -> Result<Product> {
+        let _latency = self.latency.start("product.write", "deactivate_product");
         let request = UpdateProductRequest {
             status: Some(ProductStatus::Inactive),
             ..Default::default()
@@ -670,81 +773,55 @@ impl ProductService for DefaultProductService {
         self.update_product(product_id, request).await
     }
 
+    #[tracing::instrument(skip_all, fields(
+        tenant_id = %self.tenant_context.tenant_id,
+        product_id = %product_id,
+        replacement_id = replacement_id.map(tracing::field::display)
+    ))]
     async fn discontinue_product(&self, product_id: Uuid, replacement_id: Option<Uuid>) -> Result<Product> {
-        // Update product status
-        let request = UpdateProductRequest {
-            status: Some(ProductStatus::Discontinued),
-            ..Default::default()
+        let _latency = self.latency.start("product.write", "discontinue_product");
+        // The status and the end of life are stored together or not at all
+        let change = StageChange {
+            product_id,
+            to: LifecycleStage::EndOfLife,
+            source: StageChangeSource::Manual,
+            rule: None,
+            evidence: None,
+            changed_by: self.tenant_context.user_id,
+            changed_at: Utc::now(),
         };
-        let product = self.update_product(product_id, request).await?;
-
-        // Update lifecycle
-        let lifecycle = self.repository.get_products_by_lifecycle_stage(self.tenant_context.tenant_id, LifecycleStage::Maturity).await?
-            .into_iter()
-            .find(|p| p.id == product_id)
-            .map(|_| ProductLifecycle {
-                id: Uuid::new_v4(),
-                product_id,
-                tenant_id: self.tenant_context.tenant_id,
-                stage: LifecycleStage::EndOfLife,
-                stage_changed_at: Utc::now(),
-                previous_stage: Some(LifecycleStage::Maturity),
-                time_to_market: None,
-                development_cost: None,
-                total_revenue: 0,
-                units_produced: 0,
-                units_sold: 0,
-                planned_eol_date: Some(Utc::now()),
-                replacement_product_id: replacement_id,
-                sunset_strategy: Some("Discontinued due to business decision".to_string()),
-                total_carbon_footprint: None,
-                materials_recycled: None,
-                waste_generated: None,
-                notes: None,
-                created_at: Utc::now(),
-                updated_at: Utc::now(),
-                created_by: self.tenant_context.user_id,
-                updated_by: self.tenant_context.user_id,
-            });
-
-        if let Some(lifecycle_record) = lifecycle {
-            let _updated_lifecycle = self.repository.create_lifecycle_record(&lifecycle_record).await?;
-        }
-
-        Ok(product)
-    }
-
+        self.repository
+            .discontinue_product(
+                self.tenant_context.tenant_id,
+                &change,
+                replacement_id,
+                "Discontinued due to business decision",
+            )
+            .instrument(spans::repository("discontinue_product"))
+            .await
+    }
+
+    #[tracing::instrument(skip_all, fields(tenant_id = %self.tenant_context.tenant_id))]
     async fn search_products(&self, search: AdvancedProductSearch, pagination: PaginationOptions) -> Result<PaginationResult<ProductSummary>> {
-        // Convert model::AdvancedProductSearch to repository::AdvancedProductSearch
-        let repo_search = RepoAdvancedSearch {
-            query: search.query,
-            category_ids: search.category_ids,
-            statuses: search.statuses,
-            product_types: search.product_types,
-            min_price: search.min_price.map(|p| p as f64),
-            max_price: search.max_price.map(|p| p as f64),
-            supplier_ids: None,
-            tags: search.tags,
-            in_stock_only: search.in_stock_only,
-            needs_reorder: None,
-            featured_only: search.featured_only,
-            digital_only: None,
-            sort_by: None,
-            sort_order: None,
-            fuzzy_search: search.fuzzy_search,
-            include_inactive: search.include_inactive,
-        };
+        let _latency = self.latency.start("product.search", "search_products");
+        let repo_search = RepoAdvancedSearch::from(search);
         // Convert PaginationOptions to repository PaginationOptions
         let repo_pagination = super::repository::PaginationOptions {
             page: pagination.page() as i64,
             limit: pagination.per_page() as i64,
         };
-        self.repository.search_products_advanced(self.tenant_context.tenant_id, &repo_search, &repo_pagination).await
+        self.repository.search_products_advanced(self.tenant_context.tenant_id, &repo_search, &repo_pagination)
+            .instrument(spans::repository("search_products_advanced"))
+            .await
     }
 
+    #[tracing::instrument(skip_all, fields(tenant_id = %self.tenant_context.tenant_id))]
     async fn search_products_with_ai(&self, query: &str, context: &SearchContext) -> Result<Vec<ProductRecommendation>> {
+        let _latency = self.latency.start("product.search", "search_products_with_ai");
         // AI-powered semantic search
-        let semantic_results = self.ai_engine.semantic_search(query, context).await?;
+        let semantic_results = self.ai_engine.semantic_search(query, context)
+            .instrument(spans::engine("ai", "semantic_search"))
+            .await?;
 
         let mut recommendations = Vec::new();
         for result in semantic_results {
@@ -762,15 +839,23 @@ impl ProductService for DefaultProductService {
         Ok(recommendations)
     }
 
+    #[tracing::instrument(skip_all, fields(tenant_id = %self.tenant_context.tenant_id, product_id = %product_id))]
     async fn find_similar_products(&self, product_id: Uuid, similarity_threshold: f64) -> Result<Vec<ProductSummary>> {
-        let product = self.repository.get_product_by_id(self.tenant_context.tenant_id, product_id).await?
+        let _latency = self.latency.start("product.search", "find_similar_products");
+        let product = self.repository.get_product_by_id(self.tenant_context.tenant_id, product_id)
+            .instrument(spans::repository("get_product_by_id"))
+            .await?
             .ok_or_else(|| Error::new(ErrorCode::NotFound, "Product not found"))?;
 
-        let similar_products = self.ai_engine.find_similar_products(&product, similarity_threshold).await?;
+        let similar_products = self.ai_engine.find_similar_products(&product, similarity_threshold)
+            .instrument(spans::engine("ai", "find_similar_products"))
+            .await?;
 
         let mut results = Vec::new();
         for similar in similar_products {
-            if let Some(similar_product) = self.repository.get_product_by_id(self.tenant_context.tenant_id, similar.product_id).await? {
+            if let Some(similar_product) = self.repository.get_product_by_id(self.tenant_context.tenant_id, similar.product_id)
+                .instrument(spans::repository("get_product_by_id"))
+                .await? {
                 results.push(ProductSummary {
                     id: similar_product.id,
                     sku: similar_product.sku.clone(),
@@ -792,12 +877,18 @@ impl ProductService for DefaultProductService {
         Ok(results)
     }
 
+    #[tracing::instrument(skip_all, fields(tenant_id = %self.tenant_context.tenant_id))]
     async fn get_trending_products(&self, period_days: i32, limit: i32) -> Result<Vec<ProductSummary>> {
-        let analytics = self.repository.get_top_performing_products(self.tenant_context.tenant_id, "trend_score", limit).await?;
+        let _latency = self.latency.start("product.analytics", "get_trending_products");
+        let analytics = self.repository.get_top_performing_products(self.tenant_context.tenant_id, "trend_score", limit)
+            .instrument(spans::repository("get_top_performing_products"))
+            .await?;
 
         let mut trending_products = Vec::new();
         for analytic in analytics {
-            if let Some(product) = self.repository.get_product_by_id(self.tenant_context.tenant_id, analytic.product_id).await? {
+            if let Some(product) = self.repository.get_product_by_id(self.tenant_context.tenant_id, analytic.product_id)
+                .instrument(spans::repository("get_product_by_id"))
+                .await? {
                 trending_products.push(ProductSummary {
                     id: product.id,
                     sku: product.sku.clone(),
@@ -822,7 +913,9 @@ impl ProductService for DefaultProductService {
     // Placeholder implementations for other complex methods
     // Each would have full business logic in a real implementation
 
+    #[tracing::instrument(skip_all, fields(tenant_id = %self.tenant_context.tenant_id))]
     async fn create_category(&self, request: CreateCategoryRequest) -> Result<ProductCategory> {
+        let _latency = self.latency.start("product.write", "create_category");
         let category = ProductCategory::new(
             self.tenant_context.tenant_id,
             request.name.clone(),
@@ -831,14 +924,24 @@ impl ProductService for DefaultProductService {
             self.tenant_context.user_id,
         );
 
-        self.repository.create_category(&category).await
+        self.repository.create_category(&category).instrument(spans::repository("create_category")).await
     }
 
+    #[tracing::instrument(skip_all, fields(tenant_id = %self.tenant_context.tenant_id))]
     async fn get_category_hierarchy(&self) -> Result<Vec<ProductCategory>> {
-        self.repository.get_category_hierarchy(self.tenant_context.tenant_id).await
+        let _latency = self.latency.start("product.read", "get_category_hierarchy");
+        self.repository.get_category_hierarchy(self.tenant_context.tenant_id)
+            .instrument(spans::repository("get_category_hierarchy"))
+            .await
     }
 
+    #[tracing::instrument(skip_all, fields(
+        tenant_id = %self.tenant_context.tenant_id,
+        product_id = %product_id,
+        category_id = category_id.map(tracing::field::display)
+    ))]
     async fn move_product_to_category(&self, product_id: Uuid, category_id: Option<Uuid>) -> Result<Product> {
+        let _latency = self.latency.start("product.write", "move_product_to_category");
         let request = UpdateProductRequest {
             category_id,
             ..Default::default()
@@ -846,8 +949,12 @@ impl ProductService for DefaultProductService {
         self.update_product(product_id, request).await
     }
 
+    #[tracing::instrument(skip_all, fields(tenant_id = %self.tenant_context.tenant_id, product_id = %product_id))]
     async fn auto_categorize_product(&self, product_id: Uuid) -> Result<Option<Uuid>> {
-        let product = self.repository.get_product_by_id(self.tenant_context.tenant_id, product_id).await?
+        let _latency = self.latency.start("product.write", "auto_categorize_product");
+        let product = self.repository.get_product_by_id(self.tenant_context.tenant_id, product_id)
+            .instrument(spans::repository("get_product_by_id"))
+            .await?
             .ok_or_else(|| Error::new(ErrorCode::NotFound, "Product not found"))?;
 
         let category_id = self.determine_category(&product).await?;
@@ -859,26 +966,42 @@ impl ProductService for DefaultProductService {
         Ok(category_id)
     }
 
+    #[tracing::instrument(skip_all, fields(tenant_id = %self.tenant_context.tenant_id))]
     async fn optimize_category_structure(&self) -> Result<Vec<CategoryOptimizationSuggestion>> {
-        let suggestions = self.ai_engine.optimize_categories(self.tenant_context.tenant_id).await?;
+        let _latency = self.latency.start("product.analytics", "optimize_category_structure");
+        let suggestions = self.ai_engine.optimize_categories(self.tenant_context.tenant_id)
+            .instrument(spans::engine("ai", "optimize_categories"))
+            .await?;
         Ok(suggestions)
     }
 
     // Continued implementation of all other methods...
     // For brevity, showing the pattern. Each method would have comprehensive implementation.
 
+    #[tracing::instrument(skip_all, fields(tenant_id = %self.tenant_context.tenant_id, product_id = %product_id))]
     async fn get_product_inventory(&self, product_id: Uuid) -> Result<Vec<ProductInventory>> {
-        self.repository.get_product_inventory(self.tenant_context.tenant_id, product_id).await
+        let _latency = self.latency.start("product.read", "get_product_inventory");
+        self.repository.get_product_inventory(self.tenant_context.tenant_id, product_id)
+            .instrument(spans::repository("get_product_inventory"))
+            .await
     }
 
+    #[tracing::instrument(skip_all, fields(
+        tenant_id = %self.tenant_context.tenant_id,
+        product_id = %product_id,
+        location_id = %location_id
+    ))]
     async fn update_stock_level(&self, product_id: Uuid, location_id: Uuid, adjustment: StockAdjustmentRequest) -> Result<ProductInventory> {
+        let _latency = self.latency.start("product.write", "update_stock_level");
         // Validate adjustment
         if adjustment.quantity == 0 {
             return Err(Error::new(ErrorCode::ValidationFailed, "Adjustment quantity cannot be zero"));
         }
 
         // Get current inventory
-        let inventories = self.repository.get_product_inventory(self.tenant_context.tenant_id, product_id).await?;
+        let inventories = self.repository.get_product_inventory(self.tenant_context.tenant_id, product_id)
+            .instrument(spans::repository("get_product_inventory"))
+            .await?;
         let current_inventory = inventories.iter().find(|inv| inv.location_id == location_id);
 
         let new_stock = match adjustment.adjustment_type {
@@ -892,10 +1015,14 @@ impl ProductService for DefaultProductService {
             StockAdjustmentType::Set => adjustment.quantity,
         };
 
-        self.repository.update_stock_level(self.tenant_context.tenant_id, product_id, location_id, new_stock).await?;
+        self.repository.update_stock_level(self.tenant_context.tenant_id, product_id, location_id, new_stock)
+            .instrument(spans::repository("update_stock_level"))
+            .await?;
 
         // Return updated inventory
-        let updated_inventories = self.repository.get_product_inventory(self.tenant_context.tenant_id, product_id).await?;
+        let updated_inventories = self.repository.get_product_inventory(self.tenant_context.tenant_id, product_id)
+            .instrument(spans::repository("get_product_inventory"))
+            .await?;
         updated_inventories.into_iter()
             .find(|inv| inv.location_id == location_id)
             .ok_or_else(|| Error::new(ErrorCode::InternalServerError, "Failed to retrieve updated inventory"))
@@ -904,12 +1031,21 @@ impl ProductService for DefaultProductService {
     // Continue with remaining method implementations...
     // For space reasons, implementing key methods and using placeholders for others
 
+    #[tracing::instrument(skip_all, fields(
+        tenant_id = %self.tenant_context.tenant_id,
+        location_id = location_id.map(tracing::field::display)
+    ))]
     async fn get_reorder_recommendations(&self, location_id: Option<Uuid>) -> Result<Vec<ReorderRecommendation>> {
-        let low_stock_products = self.repository.get_products_needing_reorder(self.tenant_context.tenant_id, location_id).await?;
+        let _latency = self.latency.start("product.analytics", "get_reorder_recommendations");
+        let low_stock_products = self.repository.get_products_needing_reorder(self.tenant_context.tenant_id, location_id)
+            .instrument(spans::repository("get_products_needing_reorder"))
+            .await?;
 
         let mut recommendations = Vec::new();
         for product in low_stock_products {
-            let demand_forecast = self.ai_engine.forecast_demand(product.id, 30).await?;
+            let demand_forecast = self.ai_engine.forecast_demand(product.id, 30)
+                .instrument(spans::engine("ai", "forecast_demand"))
+                .await?;
 
             recommendations.push(ReorderRecommendation {
                 product_id: product.id,
@@ -929,21 +1065,31 @@ impl ProductService for DefaultProductService {
     }
 
     // Implementing more placeholder methods for completeness
+    #[tracing::instrument(skip_all, fields(tenant_id = %self.tenant_context.tenant_id))]
     async fn optimize_stock_levels(&self, _product_ids: Vec<Uuid>) -> Result<Vec<StockOptimization>> {
+        let _latency = self.latency.start("product.analytics", "optimize_stock_levels");
         Ok(Vec::new()) // Placeholder
     }
 
+    #[tracing::instrument(skip_all, fields(tenant_id = %self.tenant_context.tenant_id, product_id = %product_id))]
     async fn forecast_demand(&self, product_id: Uuid, days_ahead: i32) -> Result<DemandForecast> {
-        self.ai_engine.forecast_demand(product_id, days_ahead).await
+        let _latency = self.latency.start("product.analytics", "forecast_demand");
+        self.ai_engine.forecast_demand(product_id, days_ahead).instrument(spans::engine("ai", "forecast_demand")).await
     }
 
+    #[tracing::instrument(skip_all, fields(tenant_id = %self.tenant_context.tenant_id, product_id = %product_id))]
     async fn calculate_safety_stock(&self, product_id: Uuid, service_level: f64) -> Result<i32> {
-        let forecast = self.ai_engine.forecast_demand(product_id, 30).await?;
+        let _latency = self.latency.start("product.analytics", "calculate_safety_stock");
+        let forecast = self.ai_engine.forecast_demand(product_id, 30)
+            .instrument(spans::engine("ai", "forecast_demand"))
+            .await?;
         let safety_stock = (forecast.demand_variance.sqrt() * service_level) as i32;
         Ok(safety_stock.max(1))
     }
 
+    #[tracing::instrument(skip_all, fields(tenant_id = %self.tenant_context.tenant_id, product_id = %product_id))]
     async fn create_pricing_rule(&self, product_id: Uuid, rule: DynamicPriceRule) -> Result<DynamicPrice> {
+        let _latency = self.latency.start("product.write", "create_pricing_rule");
         // Create a dynamic pricing rule for the product
         Ok(DynamicPrice {
             id: Uuid::new_v4(),
@@ -972,22 +1118,40 @@ impl ProductService for DefaultProductService {
         })
     }
 
+    #[tracing::instrument(skip_all, fields(tenant_id = %self.tenant_context.tenant_id, product_id = %product_id))]
     async fn get_effective_price(&self, product_id: Uuid, context: &PriceContext) -> Result<EffectivePrice> {
-        let product = self.repository.get_product_by_id(self.tenant_context.tenant_id, product_id).await?
+        let _latency = self.latency.start("product.read", "get_effective_price");
+        if let Some(price_lists) = &self.price_lists {
+            return price_lists.effective_price(product_id, None, context).await;
+        }
+
+        let product = self.repository.get_product_by_id(self.tenant_context.tenant_id, product_id)
+            .instrument(spans::repository("get_product_by_id"))
+            .await?
             .ok_or_else(|| Error::new(ErrorCode::NotFound, "Product not found"))?;
 
-        let prices = self.repository.get_product_prices(self.tenant_context.tenant_id, product_id).await?;
-        let effective_price = self.pricing_engine.calculate_effective_price(&product, &prices, context).await?;
+        let prices = self.repository.get_product_prices(self.tenant_context.tenant_id, product_id)
+            .instrument(spans::repository("get_product_prices"))
+            .await?;
+        let effective_price = self.pricing_engine.calculate_effective_price(&product, &prices, context)
+            .instrument(spans::engine("pricing", "calculate_effective_price"))
+            .await?;
 
         Ok(effective_price)
     }
 
+    #[tracing::instrument(skip_all, fields(tenant_id = %self.tenant_context.tenant_id))]
     async fn optimize_pricing(&self, product_ids: Vec<Uuid>, strategy: PricingStrategy) -> Result<Vec<PriceOptimization>> {
+        let _latency = self.latency.start("product.analytics", "optimize_pricing");
         let mut optimizations = Vec::new();
 
         for product_id in product_ids {
-            if let Some(product) = self.repository.get_product_by_id(self.tenant_context.tenant_id, product_id).await? {
-                let optimization = self.pricing_engine.optimize_product_price(&product, &strategy).await?;
+            if let Some(product) = self.repository.get_product_by_id(self.tenant_context.tenant_id, product_id)
+                .instrument(spans::repository("get_product_by_id"))
+                .await? {
+                let optimization = self.pricing_engine.optimize_product_price(&product, &strategy)
+                    .instrument(spans::engine("pricing", "optimize_product_price"))
+                    .await?;
                 optimizations.push(optimization);
             }
         }
@@ -995,25 +1159,45 @@ impl ProductService for DefaultProductService {
         Ok(optimizations)
     }
 
+    #[tracing::instrument(skip_all, fields(tenant_id = %self.tenant_context.tenant_id))]
     async fn bulk_update_prices(&self, updates: BulkPriceUpdateRequest) -> Result<BulkUpdateResult> {
-        let affected_rows = self.repository.bulk_update_prices(self.tenant_context.tenant_id, &updates).await?;
-
-        Ok(BulkUpdateResult {
-            total_products: updates.product_ids.len() as i32,
-            successful_updates: affected_rows as i32,
-            failed_updates: (updates.product_ids.len() as i64 - affected_rows) as i32,
-            errors: Vec::new(),
-        })
+        let _latency = self.latency.start("product.write", "bulk_update_prices");
+        let total_products = updates.product_ids.len() as i32;
+        let outcome = self.bulk_price_updates.bulk_update_prices(updates).await?;
+        Ok(BulkUpdateResult::new(total_products, outcome))
     }
 
+    #[tracing::instrument(skip_all, fields(tenant_id = %self.tenant_context.tenant_id, product_id = %product_id))]
     async fn calculate_landed_cost(&self, product_id: Uuid, quantity: i32, destination: &str) -> Result<LandedCost> {
-        let product = self.repository.get_product_by_id(self.tenant_context.tenant_id, product_id).await?
+        let _latency = self.latency.start("product.analytics", "calculate_landed_cost");
+        let product = self.repository.get_product_by_id(self.tenant_context.tenant_id, product_id)
+            .instrument(spans::repository("get_product_by_id"))
+            .await?
             .ok_or_else(|| Error::new(ErrorCode::NotFound, "Product not found"))?;
 
-        let base_cost = product.cost_price.unwrap_or(product.base_price) * quantity as i64;
-        let shipping_cost = self.pricing_engine.calculate_shipping_cost(&product, quantity, destination).await?;
-        let duties_and_taxes = self.pricing_engine.calculate_duties_and_taxes(&product, quantity, destination).await?;
-        let handling_fees = self.pricing_engine.calculate_handling_fees(&product, quantity).await?;
+        // Price at the tier the order reaches with the best valid supplier entry
+        let quote = match &self.supplier_catalog {
+            Some(catalog) => catalog.quote_product(product_id, CatalogQuoteRequest {
+                quantity,
+                ..Default::default()
+            }).await?,
+            None => None,
+        };
+        let unit_cost = match &quote {
+            Some(quote) => self.convert_price(quote.unit_price, &quote.currency, &product.currency).await?,
+            None => product.cost_price.unwrap_or(product.base_price),
+        };
+
+        let base_cost = unit_cost * quantity as i64;
+        let shipping_cost = self.pricing_engine.calculate_shipping_cost(&product, quantity, destination)
+            .instrument(spans::engine("pricing", "calculate_shipping_cost"))
+            .await?;
+        let duties_and_taxes = self.pricing_engine.calculate_duties_and_taxes(&product, quantity, destination)
+            .instrument(spans::engine("pricing", "calculate_duties_and_taxes"))
+            .await?;
+        let handling_fees = self.pricing_engine.calculate_handling_fees(&product, quantity)
+            .instrument(spans::engine("pricing", "calculate_handling_fees"))
+            .await?;
 
         Ok(LandedCost {
             product_cost: base_cost,
@@ -1022,18 +1206,28 @@ impl ProductService for DefaultProductService {
             handling_fees,
             total_landed_cost: base_cost + shipping_cost + duties_and_taxes + handling_fees,
             cost_per_unit: (base_cost + shipping_cost + duties_and_taxes + handling_fees) / quantity as i64,
+            supplier_id: quote.as_ref().map(|q| q.supplier_id),
+            catalog_entry_id: quote.as_ref().map(|q| q.entry_id),
         })
     }
 
+    #[tracing::instrument(skip_all, fields(tenant_id = %self.tenant_context.tenant_id, product_id = %product_id))]
     async fn analyze_price_competitiveness(&self, product_id: Uuid) -> Result<CompetitivenessAnalysis> {
-        let product = self.repository.get_product_by_id(self.tenant_context.tenant_id, product_id).await?
+        let _latency = self.latency.start("product.analytics", "analyze_price_competitiveness");
+        let product = self.repository.get_product_by_id(self.tenant_context.tenant_id, product_id)
+            .instrument(spans::repository("get_product_by_id"))
+            .await?
             .ok_or_else(|| Error::new(ErrorCode::NotFound, "Product not found"))?;
 
-        self.pricing_engine.analyze_market_competitiveness(&product).await
+        self.pricing_engine.analyze_market_competitiveness(&product)
+            .instrument(spans::engine("pricing", "analyze_market_competitiveness"))
+            .await
     }
 
     // Quality & Compliance methods
+    #[tracing::instrument(skip_all, fields(tenant_id = %self.tenant_context.tenant_id, product_id = %product_id))]
     async fn create_product_batch(&self, product_id: Uuid, batch_data: BatchCreationRequest) -> Result<ProductBatch> {
+        let _latency = self.latency.start("product.write", "create_product_batch");
         let batch = ProductBatch {
             id: Uuid::new_v4(),
             product_id,
@@ -1063,10 +1257,12 @@ impl ProductService for DefaultProductService {
             updated_by: self.tenant_context.user_id,
         };
 
-        self.repository.create_batch(&batch).await
+        self.repository.create_batch(&batch).instrument(spans::repository("create_batch")).await
     }
 
+    #[tracing::instrument(skip_all, fields(tenant_id = %self.tenant_context.tenant_id, batch_id = %batch_id))]
     async fn update_batch_quality(&self, batch_id: Uuid, quality_update: QualityUpdate) -> Result<ProductBatch> {
+        let _latency = self.latency.start("product.write", "update_batch_quality");
         // Update batch with new quality information
         Ok(ProductBatch {
             id: batch_id,
@@ -1098,8 +1294,12 @@ impl ProductService for DefaultProductService {
         })
     }
 
+    #[tracing::instrument(skip_all, fields(tenant_id = %self.tenant_context.tenant_id, product_id = %product_id))]
     async fn trace_product_lineage(&self, product_id: Uuid, batch_number: Option<String>) -> Result<ProductLineage> {
-        let batches = self.repository.get_product_batches(self.tenant_context.tenant_id, product_id).await?;
+        let _latency = self.latency.start("product.read", "trace_product_lineage");
+        let batches = self.repository.get_product_batches(self.tenant_context.tenant_id, product_id)
+            .instrument(spans::repository("get_product_batches"))
+            .await?;
 
         let target_batch = if let Some(batch_num) = batch_number {
             batches.into_iter().find(|b| b.batch_number == batch_num)
@@ -1108,7 +1308,9 @@ impl ProductService for DefaultProductService {
         };
 
         if let Some(batch) = target_batch {
-            let lineage = self.repository.trace_batch_lineage(self.tenant_context.tenant_id, batch.id).await?;
+            let lineage = self.repository.trace_batch_lineage(self.tenant_context.tenant_id, batch.id)
+                .instrument(spans::repository("trace_batch_lineage"))
+                .await?;
             Ok(ProductLineage {
                 product_id,
                 batch_id: batch.id,
@@ -1124,32 +1326,57 @@ impl ProductService for DefaultProductService {
         }
     }
 
+    #[tracing::instrument(skip_all, fields(tenant_id = %self.tenant_context.tenant_id, product_id = %product_id))]
     async fn check_compliance_status(&self, product_id: Uuid) -> Result<ComplianceStatus> {
-        let product = self.repository.get_product_by_id(self.tenant_context.tenant_id, product_id).await?
+        let _latency = self.latency.start("product.read", "check_compliance_status");
+        let product = self.repository.get_product_by_id(self.tenant_context.tenant_id, product_id)
+            .instrument(spans::repository("get_product_by_id"))
+            .await?
             .ok_or_else(|| Error::new(ErrorCode::NotFound, "Product not found"))?;
 
-        let attributes = self.repository.get_product_attributes(self.tenant_context.tenant_id, product_id).await?;
+        let attributes = self.repository.get_product_attributes(self.tenant_context.tenant_id, product_id)
+            .instrument(spans::repository("get_product_attributes"))
+            .await?;
 
-        let compliance_status = self.quality_engine.check_product_compliance(&product, attributes.as_ref()).await?;
+        let compliance_status = self.quality_engine.check_product_compliance(&product, attributes.as_ref())
+            .instrument(spans::engine("quality", "check_product_compliance"))
+            .await?;
         Ok(compliance_status)
     }
 
+    #[tracing::instrument(skip_all, fields(tenant_id = %self.tenant_context.tenant_id, product_id = %product_id))]
     async fn schedule_quality_inspection(&self, product_id: Uuid, inspection_type: &str) -> Result<QualityInspection> {
-        let inspection = self.quality_engine.schedule_inspection(product_id, inspection_type, self.tenant_context.user_id).await?;
+        let _latency = self.latency.start("product.write", "schedule_quality_inspection");
+        let inspection = self.quality_engine.schedule_inspection(product_id, inspection_type, self.tenant_context.user_id)
+            .instrument(spans::engine("quality", "schedule_inspection"))
+            .await?;
         Ok(inspection)
     }
 
+    #[tracing::instrument(skip_all, fields(tenant_id = %self.tenant_context.tenant_id))]
     async fn handle_product_recall(&self, product_ids: Vec<Uuid>, recall_reason: &str) -> Result<RecallResult> {
-        let recall_result = self.quality_engine.initiate_recall(product_ids, recall_reason, self.tenant_context.user_id).await?;
+        let _latency = self.latency.start("product.write", "handle_product_recall");
+        let recall_result = self.quality_engine.initiate_recall(product_ids, recall_reason, self.tenant_context.user_id)
+            .instrument(spans::engine("quality", "initiate_recall"))
+            .await?;
         Ok(recall_result)
     }
 
     // Lifecycle management
+    #[tracing::instrument(skip_all, fields(tenant_id = %self.tenant_context.tenant_id, product_id = %product_id))]
     async fn advance_lifecycle_stage(&self, product_id: Uuid, new_stage: LifecycleStage) -> Result<ProductLifecycle> {
-        self.repository.update_lifecycle_stage(self.tenant_context.tenant_id, product_id, new_stage).await
+        let _latency = self.latency.start("product.write", "advance_lifecycle_stage");
+        if let Some(lifecycle) = &self.lifecycle {
+            return lifecycle.set_stage(product_id, new_stage).await;
+        }
+        self.repository.update_lifecycle_stage(self.tenant_context.tenant_id, product_id, new_stage)
+            .instrument(spans::repository("update_lifecycle_stage"))
+            .await
     }
 
+    #[tracing::instrument(skip_all, fields(tenant_id = %self.tenant_context.tenant_id, product_id = %product_id))]
     async fn plan_product_retirement(&self, product_id: Uuid, retirement_plan: RetirementPlan) -> Result<ProductLifecycle> {
+        let _latency = self.latency.start("product.write", "plan_product_retirement");
         let lifecycle = ProductLifecycle {
             id: Uuid::new_v4(),
             product_id,
@@ -1157,6 +1384,9 @@ impl ProductService for DefaultProductService {
             stage: LifecycleStage::EndOfLife,
             stage_changed_at: Utc::now(),
             previous_stage: Some(LifecycleStage::Decline),
+            stage_set_by_automation: false,
+            stage_evidence: None,
+            launched_at: None,
             time_to_market: None,
             development_cost: None,
             total_revenue: 0,
@@ -1175,131 +1405,272 @@ impl ProductService for DefaultProductService {
             updated_by: self.tenant_context.user_id,
         };
 
-        self.repository.create_lifecycle_record(&lifecycle).await
+        self.repository.create_lifecycle_record(&lifecycle)
+            .instrument(spans::repository("create_lifecycle_record"))
+            .await
     }
 
+    #[tracing::instrument(skip_all, fields(tenant_id = %self.tenant_context.tenant_id, product_id = %product_id))]
     async fn analyze_product_performance(&self, product_id: Uuid, analysis_period: AnalysisPeriod) -> Result<ProductPerformanceReport> {
-        let analytics = self.repository.get_product_analytics(self.tenant_context.tenant_id, product_id, &analysis_period.period_type).await?;
+        let _latency = self.latency.start("product.analytics", "analyze_product_performance");
+        let analytics = self.repository.get_product_analytics(self.tenant_context.tenant_id, product_id, &analysis_period.period_type)
+            .instrument(spans::repository("get_product_analytics"))
+            .await?;
         let analytics_json = serde_json::to_value(&analytics).map_err(|e| Error::new(ErrorCode::InternalServerError, format!("Serialization error: {}", e)))?;
-        let report = self.analytics.generate_performance_report(product_id, &analytics_json, &analysis_period).await
+        let report = self.analytics.generate_performance_report(product_id, &analytics_json, &analysis_period)
+            .instrument(spans::engine("analytics", "generate_performance_report"))
+            .await
             .map_err(|e| Error::new(ErrorCode::InternalServerError, format!("Analytics error: {}", e)))?;
         Ok(report)
     }
 
+    #[tracing::instrument(skip_all, fields(tenant_id = %self.tenant_context.tenant_id, product_id = %product_id))]
     async fn get_lifecycle_recommendations(&self, product_id: Uuid) -> Result<Vec<LifecycleRecommendation>> {
-        let recommendations = self.ai_engine.suggest_lifecycle_actions(product_id).await
-            .map_err(|e| Error::new(ErrorCode::InternalServerError, format!("AI engine error: {}", e)))?;
-        Ok(recommendations)
+        let _latency = self.latency.start("product.analytics", "get_lifecycle_recommendations");
+        let lifecycle = self.lifecycle.as_ref()
+            .ok_or_else(|| Error::new(ErrorCode::NotImplemented, "Lifecycle automation is not configured"))?;
+        let evaluation = lifecycle.evaluate_product(product_id, Utc::now()).await?;
+        Ok(evaluation.transition.iter().map(recommendation).collect())
     }
 
     // AI-powered features
+    #[tracing::instrument(skip_all, fields(tenant_id = %self.tenant_context.tenant_id, product_id = %product_id))]
+    async fn enhance_product(&self, product_id: Uuid) -> Result<Product> {
+        let _latency = self.latency.start("product.write", "enhance_product");
+        let mut product = self.repository.get_product_by_id(self.tenant_context.tenant_id, product_id)
+            .instrument(spans::repository("get_product_by_id"))
+            .await?
+            .ok_or_else(|| Error::new(ErrorCode::NotFound, "Product not found"))?;
+
+        if product.category_id.is_none() {
+            product.category_id = self.determine_category(&product).await?;
+        }
+        if product.reorder_point.is_none() {
+            product.reorder_point = self.calculate_intelligent_reorder_point(&product).await?;
+        }
+
+        // Generate AI-enhanced content
+        let ai_description = self.ai_engine.generate_description(&product)
+            .instrument(spans::engine("ai", "generate_description"))
+            .await?;
+        let seo_optimization = self.ai_engine.optimize_seo(&product)
+            .instrument(spans::engine("ai", "optimize_seo"))
+            .await?;
+        if ai_description.quality_score > 0.7 {
+            product.description = Some(ai_description.content);
+        }
+        product.meta_title = Some(seo_optimization.title);
+        product.meta_description = Some(seo_optimization.description);
+        product.updated_at = Utc::now();
+        product.updated_by = self.tenant_context.user_id;
+
+        let enhanced_product = self.repository.update_product(&product)
+            .instrument(spans::repository("update_product"))
+            .await?;
+
+        if self.suggestions.is_some() {
+            let optimization_suggestions = self.ai_engine.suggest_optimizations(&enhanced_product)
+                .instrument(spans::engine("ai", "suggest_optimizations"))
+                .await?;
+            return self.record_suggestions(enhanced_product, &optimization_suggestions, SuggestionSource::Create).await;
+        }
+
+        Ok(enhanced_product)
+    }
+
+    #[tracing::instrument(skip_all, fields(tenant_id = %self.tenant_context.tenant_id, product_id = %product_id))]
     async fn generate_product_description(&self, product_id: Uuid, style: &str) -> Result<String> {
-        let product = self.repository.get_product_by_id(self.tenant_context.tenant_id, product_id).await?
+        let _latency = self.latency.start("product.analytics", "generate_product_description");
+        let product = self.repository.get_product_by_id(self.tenant_context.tenant_id, product_id)
+            .instrument(spans::repository("get_product_by_id"))
+            .await?
             .ok_or_else(|| Error::new(ErrorCode::NotFound, "Product not found"))?;
 
-        let description = self.ai_engine.generate_description_with_style(&product, style).await?;
+        let description = self.ai_engine.generate_description_with_style(&product, style)
+            .instrument(spans::engine("ai", "generate_description_with_style"))
+            .await?;
         Ok(description.content)
     }
 
+    #[tracing::instrument(skip_all, fields(tenant_id = %self.tenant_context.tenant_id, product_id = %product_id))]
     async fn optimize_seo_content(&self, product_id: Uuid) -> Result<SeoOptimization> {
-        let product = self.repository.get_product_by_id(self.tenant_context.tenant_id, product_id).await?
+        let _latency = self.latency.start("product.analytics", "optimize_seo_content");
+        let product = self.repository.get_product_by_id(self.tenant_context.tenant_id, product_id)
+            .instrument(spans::repository("get_product_by_id"))
+            .await?
             .ok_or_else(|| Error::new(ErrorCode::NotFound, "Product not found"))?;
 
-        self.ai_engine.optimize_seo(&product).await
+        self.ai_engine.optimize_seo(&product).instrument(spans::engine("ai", "optimize_seo")).await
     }
 
+    #[tracing::instrument(skip_all, fields(tenant_id = %self.tenant_context.tenant_id, product_id = %product_id))]
     async fn detect_anomalies(&self, product_id: Uuid) -> Result<Vec<ProductAnomaly>> {
-        let analytics = self.repository.get_product_analytics(self.tenant_context.tenant_id, product_id, "daily").await?;
-        let anomalies = self.ai_engine.detect_anomalies(product_id, &analytics).await?;
+        let _latency = self.latency.start("product.analytics", "detect_anomalies");
+        let analytics = self.repository.get_product_analytics(self.tenant_context.tenant_id, product_id, "daily")
+            .instrument(spans::repository("get_product_analytics"))
+            .await?;
+        let anomalies = self.ai_engine.detect_anomalies(product_id, &analytics)
+            .instrument(spans::engine("ai", "detect_anomalies"))
+            .await?;
         Ok(anomalies)
     }
 
+    #[tracing::instrument(skip_all, fields(tenant_id = %self.tenant_context.tenant_id))]
     async fn predict_product_success(&self, product_data: &CreateProductRequest) -> Result<SuccessPrediction> {
-        self.ai_engine.predict_success(product_data).await
+        let _latency = self.latency.start("product.analytics", "predict_product_success");
+        self.ai_engine.predict_success(product_data).instrument(spans::engine("ai", "predict_success")).await
     }
 
+    #[tracing::instrument(skip_all, fields(tenant_id = %self.tenant_context.tenant_id, product_id = %product_id))]
     async fn recommend_product_bundles(&self, product_id: Uuid) -> Result<Vec<BundleRecommendation>> {
-        let recommendations = self.ai_engine.suggest_bundles(product_id).await?;
+        let _latency = self.latency.start("product.analytics", "recommend_product_bundles");
+        let recommendations = self.ai_engine.suggest_bundles(product_id)
+            .instrument(spans::engine("ai", "suggest_bundles"))
+            .await?;
         Ok(recommendations)
     }
 
+    #[tracing::instrument(skip_all, fields(tenant_id = %self.tenant_context.tenant_id, category_id = %category_id))]
     async fn analyze_market_opportunity(&self, category_id: Uuid) -> Result<MarketOpportunityAnalysis> {
-        let analysis = self.ai_engine.analyze_market_opportunity(category_id).await?;
+        let _latency = self.latency.start("product.analytics", "analyze_market_opportunity");
+        let analysis = self.ai_engine.analyze_market_opportunity(category_id)
+            .instrument(spans::engine("ai", "analyze_market_opportunity"))
+            .await?;
         Ok(analysis)
     }
 
     // Sustainability features
+    #[tracing::instrument(skip_all, fields(tenant_id = %self.tenant_context.tenant_id, product_id = %product_id))]
     async fn calculate_carbon_footprint(&self, product_id: Uuid) -> Result<CarbonFootprint> {
-        let product = self.repository.get_product_by_id(self.tenant_context.tenant_id, product_id).await?
+        let _latency = self.latency.start("product.analytics", "calculate_carbon_footprint");
+        let product = self.repository.get_product_by_id(self.tenant_context.tenant_id, product_id)
+            .instrument(spans::repository("get_product_by_id"))
+            .await?
             .ok_or_else(|| Error::new(ErrorCode::NotFound, "Product not found"))?;
 
-        let attributes = self.repository.get_product_attributes(self.tenant_context.tenant_id, product_id).await?;
-        let footprint = self.ai_engine.calculate_carbon_footprint(&product, attributes.as_ref()).await?;
+        let attributes = self.repository.get_product_attributes(self.tenant_context.tenant_id, product_id)
+            .instrument(spans::repository("get_product_attributes"))
+            .await?;
+        let footprint = self.ai_engine.calculate_carbon_footprint(&product, attributes.as_ref())
+            .instrument(spans::engine("ai", "calculate_carbon_footprint"))
+            .await?;
         Ok(footprint)
     }
 
+    #[tracing::instrument(skip_all, fields(tenant_id = %self.tenant_context.tenant_id, product_id = %product_id))]
     async fn assess_sustainability_score(&self, product_id: Uuid) -> Result<SustainabilityScore> {
-        let product = self.repository.get_product_by_id(self.tenant_context.tenant_id, product_id).await?
+        let _latency = self.latency.start("product.analytics", "assess_sustainability_score");
+        let product = self.repository.get_product_by_id(self.tenant_context.tenant_id, product_id)
+            .instrument(spans::repository("get_product_by_id"))
+            .await?
             .ok_or_else(|| Error::new(ErrorCode::NotFound, "Product not found"))?;
 
-        let score = self.ai_engine.assess_sustainability(&product).await?;
+        let score = self.ai_engine.assess_sustainability(&product)
+            .instrument(spans::engine("ai", "assess_sustainability"))
+            .await?;
         Ok(score)
     }
 
+    #[tracing::instrument(skip_all, fields(tenant_id = %self.tenant_context.tenant_id, product_id = %product_id))]
     async fn find_eco_alternatives(&self, product_id: Uuid) -> Result<Vec<EcoAlternative>> {
-        let alternatives = self.ai_engine.find_eco_alternatives(product_id).await?;
+        let _latency = self.latency.start("product.search", "find_eco_alternatives");
+        let alternatives = self.ai_engine.find_eco_alternatives(product_id)
+            .instrument(spans::engine("ai", "find_eco_alternatives"))
+            .await?;
         Ok(alternatives)
     }
 
+    #[tracing::instrument(skip_all, fields(tenant_id = %self.tenant_context.tenant_id, product_id = %product_id))]
     async fn track_circular_economy_metrics(&self, product_id: Uuid) -> Result<CircularEconomyMetrics> {
-        let metrics = self.ai_engine.calculate_circular_metrics(product_id).await?;
+        let _latency = self.latency.start("product.analytics", "track_circular_economy_metrics");
+        let metrics = self.ai_engine.calculate_circular_metrics(product_id)
+            .instrument(spans::engine("ai", "calculate_circular_metrics"))
+            .await?;
         Ok(metrics)
     }
 
     // Integration methods
+    #[tracing::instrument(skip_all, fields(tenant_id = %self.tenant_context.tenant_id))]
     async fn sync_with_external_system(&self, system_id: &str, product_mapping: ExternalProductMapping) -> Result<SyncResult> {
-        let result = self.ai_engine.sync_external_data(system_id, &product_mapping).await?;
+        let _latency = self.latency.start("product.write", "sync_with_external_system");
+        let result = self.ai_engine.sync_external_data(system_id, &product_mapping)
+            .instrument(spans::engine("ai", "sync_external_data"))
+            .await?;
         Ok(result)
     }
 
-    async fn export_product_feed(&self, format: &str, filter: Option<AdvancedProductSearch>) -> Result<String> {
-        let feed = self.repository.export_product_catalog(self.tenant_context.tenant_id, format).await?;
-        Ok(feed)
+    #[tracing::instrument(skip_all, fields(tenant_id = %self.tenant_context.tenant_id))]
+    async fn export_product_feed(&self, format: &str, filter: Option<AdvancedProductSearch>) -> Result<ProductFeed> {
+        let _latency = self.latency.start("product.read", "export_product_feed");
+        let format = ProductFeedFormat::parse(format)?;
+        let search = filter.map(RepoAdvancedSearch::from);
+        feed::export_product_feed(self.repository.as_ref(), self.tenant_context.tenant_id, format, search.as_ref())
+            .instrument(spans::repository("export_product_catalog"))
+            .await
     }
 
+    #[tracing::instrument(skip_all, fields(tenant_id = %self.tenant_context.tenant_id, product_id = %product_id))]
     async fn schedule_automated_tasks(&self, product_id: Uuid, tasks: Vec<AutomatedTask>) -> Result<Vec<TaskSchedule>> {
-        let schedules = self.ai_engine.schedule_tasks(product_id, tasks).await?;
+        let _latency = self.latency.start("product.write", "schedule_automated_tasks");
+        let schedules = self.ai_engine.schedule_tasks(product_id, tasks)
+            .instrument(spans::engine("ai", "schedule_tasks"))
+            .await?;
         Ok(schedules)
     }
 
+    #[tracing::instrument(skip_all, fields(tenant_id = %self.tenant_context.tenant_id))]
     async fn validate_product_data(&self, product_data: &CreateProductRequest) -> Result<ValidationResult> {
-        let validation = self.ai_engine.validate_product_data(product_data).await?;
+        let _latency = self.latency.start("product.read", "validate_product_data");
+        let validation = self.ai_engine.validate_product_data(product_data)
+            .instrument(spans::engine("ai", "validate_product_data"))
+            .await?;
         Ok(validation)
     }
 
     // Analytics methods
+    #[tracing::instrument(skip_all, fields(tenant_id = %self.tenant_context.tenant_id, product_id = %product_id))]
     async fn get_product_analytics(&self, product_id: Uuid, period: AnalysisPeriod) -> Result<ProductAnalyticsReport> {
-        let analytics = self.repository.get_product_analytics(self.tenant_context.tenant_id, product_id, &period.period_type).await?;
+        let _latency = self.latency.start("product.analytics", "get_product_analytics");
+        let analytics = self.repository.get_product_analytics(self.tenant_context.tenant_id, product_id, &period.period_type)
+            .instrument(spans::repository("get_product_analytics"))
+            .await?;
         let analytics_json = serde_json::to_value(&analytics).map_err(|e| Error::new(ErrorCode::InternalServerError, format!("Serialization error: {}", e)))?;
-        let report = self.analytics.generate_analytics_report(product_id, &analytics_json, &period).await
+        let report = self.analytics.generate_analytics_report(product_id, &analytics_json, &period)
+            .instrument(spans::engine("analytics", "generate_analytics_report"))
+            .await
             .map_err(|e| Error::new(ErrorCode::InternalServerError, format!("Analytics error: {}", e)))?;
         Ok(report)
     }
 
+    #[tracing::instrument(skip_all, fields(tenant_id = %self.tenant_context.tenant_id))]
     async fn get_inventory_turnover_analysis(&self) -> Result<Vec<TurnoverAnalysis>> {
-        let analysis = self.analytics.calculate_inventory_turnover(self.tenant_context.tenant_id).await
+        let _latency = self.latency.start("product.analytics", "get_inventory_turnover_analysis");
+        let analysis = self.analytics.calculate_inventory_turnover(self.tenant_context.tenant_id)
+            .instrument(spans::engine("analytics", "calculate_inventory_turnover"))
+            .await
             .map_err(|e| Error::new(ErrorCode::InternalServerError, format!("Analytics error: {}", e)))?;
         Ok(analysis)
     }
 
+    #[tracing::instrument(skip_all, fields(
+        tenant_id = %self.tenant_context.tenant_id,
+        category_id = category_id.map(tracing::field::display)
+    ))]
     async fn get_profitability_analysis(&self, category_id: Option<Uuid>) -> Result<ProfitabilityReport> {
-        let report = self.analytics.generate_profitability_report(self.tenant_context.tenant_id, category_id).await
+        let _latency = self.latency.start("product.analytics", "get_profitability_analysis");
+        let report = self.analytics.generate_profitability_report(self.tenant_context.tenant_id, category_id)
+            .instrument(spans::engine("analytics", "generate_profitability_report"))
+            .await
             .map_err(|e| Error::new(ErrorCode::InternalServerError, format!("Analytics error: {}", e)))?;
         Ok(report)
     }
 
+    #[tracing::instrument(skip_all, fields(tenant_id = %self.tenant_context.tenant_id, product_id = %product_id))]
     async fn get_market_share_analysis(&self, product_id: Uuid) -> Result<MarketShareAnalysis> {
-        let analysis = self.analytics.analyze_market_share(product_id).await
+        let _latency = self.latency.start("product.analytics", "get_market_share_analysis");
+        let analysis = self.analytics.analyze_market_share(product_id)
+            .instrument(spans::engine("analytics", "analyze_market_share"))
+            .await
             .map_err(|e| Error::new(ErrorCode::InternalServerError, format!("Analytics error: {}", e)))?;
         Ok(analysis)
     }
@@ -1319,6 +1690,7 @@ impl Default for UpdateProductRequest {
             cost_price: None,
             list_price: None,
             is_tracked: None,
+            is_serialized: None,
             current_stock: None,
             min_stock_level: None,
             max_stock_level: None,
@@ -1554,14 +1926,42 @@ pub struct PriceOptimization {
     pub confidence: f64,
 }
 
+/// Bulk price update as counted per product. Nothing is written for a preview
+/// or a blocked update, so all of its products count as failed.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BulkUpdateResult {
     pub total_products: i32,
     pub successful_updates: i32,
     pub failed_updates: i32,
     pub errors: Vec<String>,
+    /// The diff, guard results and rollback id behind the counts
+    pub outcome: BulkPriceUpdateOutcome,
 }
 
+impl BulkUpdateResult {
+    fn new(total_products: i32, outcome: BulkPriceUpdateOutcome) -> Self {
+        let successful_updates = match outcome.status {
+            BulkPriceUpdateStatus::Applied => outcome.diff.lines.len() as i32,
+            BulkPriceUpdateStatus::Preview | BulkPriceUpdateStatus::Blocked => 0,
+        };
+        let mut errors: Vec<String> = outcome.diff.missing_product_ids.iter()
+            .map(|id| format!("Product {} not found", id))
+            .collect();
+        if outcome.status == BulkPriceUpdateStatus::Blocked {
+            errors.push(format!("Blocked by {} price guard violations", outcome.diff.summary.violation_count));
+        }
+
+        Self {
+            total_products,
+            successful_updates,
+            failed_updates: total_products - successful_updates,
+            errors,
+            outcome,
+        }
+    }
+}
+
+/// Costs in the product's currency
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LandedCost {
     pub product_cost: i64,
@@ -1570,6 +1970,9 @@ pub struct LandedCost {
     pub handling_fees: i64,
     pub total_landed_cost: i64,
     pub cost_per_unit: i64,
+    /// Supplier whose catalog entry priced the product, if any
+    pub supplier_id: Option<Uuid>,
+    pub catalog_entry_id: Option<Uuid>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -1665,17 +2068,6 @@ pub struct ComplianceViolation {
     pub resolution_required: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct QualityInspection {
-    pub id: Uuid,
-    pub product_id: Uuid,
-    pub inspection_type: String,
-    pub scheduled_date: DateTime<Utc>,
-    pub inspector_id: Uuid,
-    pub status: String,
-    pub priority: i32,
-}
-
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RecallResult {
     pub recall_id: Uuid,
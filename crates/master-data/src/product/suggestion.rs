@@ -0,0 +1,390 @@
+//! # Product Suggestions
+//!
+//! The AI engine's optimization suggestions are stored for review instead of
+//! being written to the product. Each [`ProductSuggestion`] keeps the value
+//! the product had when it was made and the product's `updated_at`; a
+//! suggestion whose product changed since is stale and gets expired instead
+//! of applied.
+//!
+//! Nothing is applied without a reviewer unless the tenant opted in with an
+//! [`AutoApplyRule`] for the suggestion type: suggestions of that type with at
+//! least the rule's confidence are applied when they are made and stored as
+//! accepted.
+//!
+//! Reviewers accept or reject pending suggestions one at a time or in bulk
+//! through [`ProductSuggestionService`](super::suggestion_service::ProductSuggestionService).
+
+use super::model::{Product, UpdateProductRequest};
+use super::service::OptimizationSuggestion;
+use chrono::{DateTime, Utc};
+use erp_core::error::{Error, ErrorCode, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+/// Suggested base price, in cents
+pub const PRICE_OPTIMIZATION: &str = "price_optimization";
+
+/// Suggested reorder point, in units
+pub const REORDER_POINT_OPTIMIZATION: &str = "reorder_point_optimization";
+
+/// Lifecycle stage transition proposed by the lifecycle automation. Its
+/// current value is the product's stage, which is not a product field; it is
+/// checked against the lifecycle record when accepted.
+pub const LIFECYCLE_STAGE: &str = "lifecycle_stage";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SuggestionStatus {
+    Pending,
+    Accepted,
+    Rejected,
+    /// The product changed before anyone decided
+    Expired,
+}
+
+impl SuggestionStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SuggestionStatus::Pending => "pending",
+            SuggestionStatus::Accepted => "accepted",
+            SuggestionStatus::Rejected => "rejected",
+            SuggestionStatus::Expired => "expired",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "pending" => Some(SuggestionStatus::Pending),
+            "accepted" => Some(SuggestionStatus::Accepted),
+            "rejected" => Some(SuggestionStatus::Rejected),
+            "expired" => Some(SuggestionStatus::Expired),
+            _ => None,
+        }
+    }
+}
+
+/// What produced a suggestion
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SuggestionSource {
+    Create,
+    Update,
+    /// A periodic analysis of the catalog
+    Job,
+}
+
+impl SuggestionSource {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SuggestionSource::Create => "create",
+            SuggestionSource::Update => "update",
+            SuggestionSource::Job => "job",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "create" => Some(SuggestionSource::Create),
+            "update" => Some(SuggestionSource::Update),
+            "job" => Some(SuggestionSource::Job),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProductSuggestion {
+    pub id: Uuid,
+    pub tenant_id: Uuid,
+    pub product_id: Uuid,
+    pub suggestion_type: String,
+    pub rationale: String,
+    pub expected_impact: Option<String>,
+    /// The product's value when the suggestion was made; null for types the
+    /// workflow cannot apply
+    pub current_value: Value,
+    pub suggested_value: Value,
+    pub confidence: f64,
+    pub status: SuggestionStatus,
+    pub source: SuggestionSource,
+    /// `updated_at` of the product the suggestion was made for
+    pub product_updated_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    pub decided_by: Option<Uuid>,
+    pub decided_at: Option<DateTime<Utc>>,
+    pub rejection_reason: Option<String>,
+}
+
+impl ProductSuggestion {
+    /// A pending suggestion for `product` as it is now
+    pub fn new(product: &Product, suggestion: &OptimizationSuggestion, source: SuggestionSource, now: DateTime<Utc>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            tenant_id: product.tenant_id,
+            product_id: product.id,
+            suggestion_type: suggestion.suggestion_type.clone(),
+            rationale: suggestion.description.clone(),
+            expected_impact: Some(suggestion.expected_impact.clone()).filter(|impact| !impact.is_empty()),
+            current_value: current_value(product, &suggestion.suggestion_type),
+            suggested_value: suggestion.suggested_value.clone(),
+            confidence: suggestion.confidence,
+            status: SuggestionStatus::Pending,
+            source,
+            product_updated_at: product.updated_at,
+            created_at: now,
+            decided_by: None,
+            decided_at: None,
+            rejection_reason: None,
+        }
+    }
+
+    pub fn decide(&mut self, status: SuggestionStatus, decided_by: Uuid, now: DateTime<Utc>) {
+        self.status = status;
+        self.decided_by = Some(decided_by);
+        self.decided_at = Some(now);
+    }
+}
+
+/// A tenant's opt-in to apply suggestions of one type without review
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AutoApplyRule {
+    pub suggestion_type: String,
+    /// Lowest confidence, between 0 and 1, that is applied
+    pub min_confidence: f64,
+}
+
+/// Which pending suggestions to list or accept; unset fields match everything
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SuggestionFilter {
+    pub product_id: Option<Uuid>,
+    pub suggestion_type: Option<String>,
+    pub min_confidence: Option<f64>,
+}
+
+impl SuggestionFilter {
+    pub fn matches(&self, suggestion: &ProductSuggestion) -> bool {
+        self.product_id.is_none_or(|id| suggestion.product_id == id)
+            && self.suggestion_type.as_ref().is_none_or(|t| &suggestion.suggestion_type == t)
+            && self.min_confidence.is_none_or(|min| suggestion.confidence >= min)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SuggestionFailure {
+    pub suggestion_id: Uuid,
+    pub message: String,
+}
+
+/// Result of accepting every pending suggestion matching a filter
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BulkAcceptOutcome {
+    pub accepted: Vec<Uuid>,
+    /// Suggestions whose product changed since they were made
+    pub expired: Vec<Uuid>,
+    /// Suggestions the product update refused; they stay pending
+    pub failed: Vec<SuggestionFailure>,
+}
+
+/// The product's value for the field a suggestion type changes
+pub fn current_value(product: &Product, suggestion_type: &str) -> Value {
+    match suggestion_type {
+        PRICE_OPTIMIZATION => Value::from(product.base_price),
+        REORDER_POINT_OPTIMIZATION => product.reorder_point.map(Value::from).unwrap_or(Value::Null),
+        _ => Value::Null,
+    }
+}
+
+/// The product update that applies a suggested value
+pub fn update_request(suggestion_type: &str, suggested_value: &Value) -> Result<UpdateProductRequest> {
+    let invalid = || {
+        Error::new(
+            ErrorCode::ValidationFailed,
+            format!("Suggested value {} is not a valid {}", suggested_value, suggestion_type),
+        )
+    };
+    match suggestion_type {
+        PRICE_OPTIMIZATION => Ok(UpdateProductRequest {
+            base_price: Some(suggested_value.as_i64().ok_or_else(invalid)?),
+            ..Default::default()
+        }),
+        REORDER_POINT_OPTIMIZATION => {
+            let reorder_point = suggested_value.as_i64().and_then(|v| i32::try_from(v).ok()).ok_or_else(invalid)?;
+            Ok(UpdateProductRequest {
+                reorder_point: Some(reorder_point),
+                ..Default::default()
+            })
+        }
+        other => Err(Error::new(
+            ErrorCode::ValidationFailed,
+            format!("Suggestions of type {} cannot be applied", other),
+        )),
+    }
+}
+
+/// Whether the product changed since the suggestion was made. Timestamps are
+/// compared at the database's microsecond precision. Lifecycle suggestions do
+/// not depend on the product's fields and never go stale here.
+pub fn is_stale(suggestion: &ProductSuggestion, product: &Product) -> bool {
+    if suggestion.suggestion_type == LIFECYCLE_STAGE {
+        return false;
+    }
+    product.updated_at.timestamp_micros() != suggestion.product_updated_at.timestamp_micros()
+        || current_value(product, &suggestion.suggestion_type) != suggestion.current_value
+}
+
+/// Whether the tenant's rules apply the suggestion without review
+pub fn auto_applies(rules: &[AutoApplyRule], suggestion: &OptimizationSuggestion) -> bool {
+    rules
+        .iter()
+        .any(|rule| rule.suggestion_type == suggestion.suggestion_type && suggestion.confidence >= rule.min_confidence)
+}
+
+pub fn validate_rule(rule: &AutoApplyRule) -> Result<()> {
+    if !matches!(rule.suggestion_type.as_str(), PRICE_OPTIMIZATION | REORDER_POINT_OPTIMIZATION) {
+        return Err(Error::new(
+            ErrorCode::ValidationFailed,
+            format!("Suggestions of type {} cannot be applied", rule.suggestion_type),
+        ));
+    }
+    if !(rule.min_confidence > 0.0 && rule.min_confidence <= 1.0) {
+        return Err(Error::new(
+            ErrorCode::ValidationFailed,
+            "Minimum confidence must be above 0 and at most 1",
+        ));
+    }
+    Ok(())
+}
+
+/// Records for the engine's suggestions about `product`. Those the tenant's
+/// rules auto-apply are applied to `product`, if its validations allow, and
+/// recorded as accepted by `user_id`; the rest are pending.
+pub fn triage(
+    product: &mut Product,
+    suggestions: &[OptimizationSuggestion],
+    rules: &[AutoApplyRule],
+    source: SuggestionSource,
+    user_id: Uuid,
+    now: DateTime<Utc>,
+) -> Vec<ProductSuggestion> {
+    let mut records = Vec::with_capacity(suggestions.len());
+    for suggestion in suggestions {
+        let mut record = ProductSuggestion::new(product, suggestion, source, now);
+        if auto_applies(rules, suggestion) {
+            let mut candidate = product.clone();
+            let applied = update_request(&suggestion.suggestion_type, &suggestion.suggested_value)
+                .and_then(|request| candidate.apply_update(request));
+            if applied.is_ok() {
+                *product = candidate;
+                record.decide(SuggestionStatus::Accepted, user_id, now);
+            }
+        }
+        records.push(record);
+    }
+    records
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn product() -> Product {
+        let mut product = Product::new(Uuid::new_v4(), "WIDGET-1".to_string(), "Widget".to_string(), Uuid::new_v4());
+        product.base_price = 1999;
+        product.reorder_point = Some(10);
+        product
+    }
+
+    fn suggestion(suggestion_type: &str, value: i64, confidence: f64) -> OptimizationSuggestion {
+        OptimizationSuggestion {
+            suggestion_type: suggestion_type.to_string(),
+            description: "Competitors sell for less".to_string(),
+            suggested_value: Value::from(value),
+            confidence,
+            expected_impact: "+4% units".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_nothing_is_applied_without_an_opt_in() {
+        let mut product = product();
+        let suggestions = [suggestion(PRICE_OPTIMIZATION, 1499, 0.99), suggestion(REORDER_POINT_OPTIMIZATION, 40, 0.97)];
+
+        let records = triage(&mut product, &suggestions, &[], SuggestionSource::Update, Uuid::new_v4(), Utc::now());
+        assert_eq!((product.base_price, product.reorder_point), (1999, Some(10)));
+        assert!(records.iter().all(|r| r.status == SuggestionStatus::Pending && r.decided_by.is_none()));
+        assert_eq!(records[0].current_value, Value::from(1999));
+        assert_eq!(records[0].suggested_value, Value::from(1499));
+    }
+
+    #[test]
+    fn test_opt_in_applies_only_its_type_above_the_floor() {
+        let mut product = product();
+        let rules = [AutoApplyRule {
+            suggestion_type: REORDER_POINT_OPTIMIZATION.to_string(),
+            min_confidence: 0.95,
+        }];
+        let suggestions = [
+            suggestion(PRICE_OPTIMIZATION, 1499, 0.99),
+            suggestion(REORDER_POINT_OPTIMIZATION, 25, 0.9),
+            suggestion(REORDER_POINT_OPTIMIZATION, 40, 0.97),
+        ];
+
+        let reviewer = Uuid::new_v4();
+        let records = triage(&mut product, &suggestions, &rules, SuggestionSource::Job, reviewer, Utc::now());
+        assert_eq!((product.base_price, product.reorder_point), (1999, Some(40)));
+        let statuses: Vec<_> = records.iter().map(|r| r.status).collect();
+        assert_eq!(statuses, [SuggestionStatus::Pending, SuggestionStatus::Pending, SuggestionStatus::Accepted]);
+        assert_eq!(records[2].decided_by, Some(reviewer));
+    }
+
+    #[test]
+    fn test_auto_apply_keeps_invalid_values_pending() {
+        let mut product = product();
+        let rules = [AutoApplyRule {
+            suggestion_type: PRICE_OPTIMIZATION.to_string(),
+            min_confidence: 0.5,
+        }];
+
+        let records = triage(&mut product, &[suggestion(PRICE_OPTIMIZATION, -5, 0.99)], &rules, SuggestionSource::Update, Uuid::new_v4(), Utc::now());
+        assert_eq!(product.base_price, 1999);
+        assert_eq!(records[0].status, SuggestionStatus::Pending);
+    }
+
+    #[test]
+    fn test_changed_products_make_suggestions_stale() {
+        let mut product = product();
+        let record = ProductSuggestion::new(&product, &suggestion(PRICE_OPTIMIZATION, 1499, 0.8), SuggestionSource::Update, Utc::now());
+        assert!(!is_stale(&record, &product));
+
+        // Read back from the database with microsecond precision
+        let mut stored = record.clone();
+        stored.product_updated_at = DateTime::from_timestamp_micros(product.updated_at.timestamp_micros()).unwrap();
+        assert!(!is_stale(&stored, &product));
+
+        product.updated_at += Duration::seconds(1);
+        assert!(is_stale(&record, &product));
+
+        let mut product = self::product();
+        let record = ProductSuggestion::new(&product, &suggestion(PRICE_OPTIMIZATION, 1499, 0.8), SuggestionSource::Update, Utc::now());
+        product.base_price = 1899;
+        assert!(is_stale(&record, &product), "a changed value is stale even with the same timestamp");
+    }
+
+    #[test]
+    fn test_update_request_rejects_unusable_values() {
+        let request = update_request(REORDER_POINT_OPTIMIZATION, &Value::from(40)).unwrap();
+        assert_eq!(request.reorder_point, Some(40));
+        assert!(request.base_price.is_none());
+
+        for (suggestion_type, value) in [
+            (PRICE_OPTIMIZATION, Value::from("cheaper")),
+            (REORDER_POINT_OPTIMIZATION, Value::from(i64::MAX)),
+            ("bundle_optimization", Value::from(1)),
+        ] {
+            let error = update_request(suggestion_type, &value).unwrap_err();
+            assert_eq!(error.code, ErrorCode::ValidationFailed);
+        }
+    }
+}
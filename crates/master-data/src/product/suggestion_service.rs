@@ -0,0 +1,547 @@
+use async_trait::async_trait;
+use chrono::Utc;
+use erp_core::error::{Error, ErrorCode, Result};
+use std::collections::{hash_map::Entry, HashMap, HashSet};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::product::lifecycle::{StageChange, StageChangeSource};
+use crate::product::model::{LifecycleStage, Product, UpdateProductRequest};
+use crate::product::repository::{ProductLifecycleRepository, ProductRepository, ProductSuggestionRepository};
+use crate::product::suggestion::*;
+use crate::types::TenantContext;
+
+/// The product update accepted suggestions go through
+#[async_trait]
+pub trait SuggestionTarget: Send + Sync {
+    async fn get_product(&self, product_id: Uuid) -> Result<Option<Product>>;
+
+    /// Validate and store the update like any other product update
+    async fn update_product(&self, product_id: Uuid, request: UpdateProductRequest) -> Result<Product>;
+}
+
+/// Product updates with the field validations of
+/// [`ProductService::update_product`](super::service::ProductService::update_product)
+/// but without its AI follow-up, so accepting a suggestion makes no new ones
+pub struct RepositoryProductUpdates {
+    repository: Arc<dyn ProductRepository>,
+    tenant_context: TenantContext,
+}
+
+impl RepositoryProductUpdates {
+    pub fn new(repository: Arc<dyn ProductRepository>, tenant_context: TenantContext) -> Self {
+        Self {
+            repository,
+            tenant_context,
+        }
+    }
+}
+
+#[async_trait]
+impl SuggestionTarget for RepositoryProductUpdates {
+    async fn get_product(&self, product_id: Uuid) -> Result<Option<Product>> {
+        self.repository.get_product_by_id(self.tenant_context.tenant_id, product_id).await
+    }
+
+    async fn update_product(&self, product_id: Uuid, request: UpdateProductRequest) -> Result<Product> {
+        let mut product = self
+            .get_product(product_id)
+            .await?
+            .ok_or_else(|| Error::new(ErrorCode::NotFound, "Product not found"))?;
+        product.apply_update(request)?;
+        product.updated_at = Utc::now();
+        product.updated_by = self.tenant_context.user_id;
+        self.repository.update_product(&product).await
+    }
+}
+
+/// Review of the AI engine's product suggestions
+#[async_trait]
+pub trait ProductSuggestionService: Send + Sync {
+    /// Pending suggestions matching the filter. Those whose product changed
+    /// since they were made are expired instead of listed.
+    async fn list_pending(&self, filter: SuggestionFilter) -> Result<Vec<ProductSuggestion>>;
+
+    /// Apply the suggested value through the product update. A suggestion whose
+    /// product changed is expired and fails with `ConflictError`.
+    async fn accept(&self, id: Uuid) -> Result<ProductSuggestion>;
+
+    async fn reject(&self, id: Uuid, reason: String) -> Result<ProductSuggestion>;
+
+    /// Accept every pending suggestion matching the filter, oldest first
+    async fn bulk_accept(&self, filter: SuggestionFilter) -> Result<BulkAcceptOutcome>;
+
+    async fn auto_apply_rules(&self) -> Result<Vec<AutoApplyRule>>;
+
+    /// Replace the tenant's opt-in; an empty list turns auto-apply off
+    async fn set_auto_apply_rules(&self, rules: Vec<AutoApplyRule>) -> Result<Vec<AutoApplyRule>>;
+}
+
+enum Decision {
+    Accepted(Box<ProductSuggestion>),
+    Expired,
+}
+
+pub struct DefaultProductSuggestionService {
+    repository: Arc<dyn ProductSuggestionRepository>,
+    products: Arc<dyn SuggestionTarget>,
+    lifecycle: Option<Arc<dyn ProductLifecycleRepository>>,
+    tenant_context: TenantContext,
+}
+
+impl DefaultProductSuggestionService {
+    pub fn new(
+        repository: Arc<dyn ProductSuggestionRepository>,
+        products: Arc<dyn SuggestionTarget>,
+        tenant_context: TenantContext,
+    ) -> Self {
+        Self {
+            repository,
+            products,
+            lifecycle: None,
+            tenant_context,
+        }
+    }
+
+    /// Apply accepted lifecycle suggestions to the product's lifecycle.
+    /// Without this they cannot be accepted.
+    pub fn with_lifecycle(mut self, lifecycle: Arc<dyn ProductLifecycleRepository>) -> Self {
+        self.lifecycle = Some(lifecycle);
+        self
+    }
+
+    async fn pending(&self, id: Uuid) -> Result<ProductSuggestion> {
+        let suggestion = self
+            .repository
+            .get_suggestion(self.tenant_context.tenant_id, id)
+            .await?
+            .ok_or_else(|| Error::new(ErrorCode::NotFound, format!("Suggestion {} not found", id)))?;
+        if suggestion.status != SuggestionStatus::Pending {
+            return Err(Error::new(
+                ErrorCode::ConflictError,
+                format!("Suggestion was already {}", suggestion.status.as_str()),
+            ));
+        }
+        Ok(suggestion)
+    }
+
+    /// Store the decision; `ConflictError` if someone else decided first
+    async fn decide(&self, mut suggestion: ProductSuggestion, status: SuggestionStatus) -> Result<ProductSuggestion> {
+        suggestion.decide(status, self.tenant_context.user_id, Utc::now());
+        if !self.repository.decide_suggestion(&suggestion).await? {
+            return Err(Error::new(ErrorCode::ConflictError, "Suggestion was decided concurrently"));
+        }
+        Ok(suggestion)
+    }
+
+    async fn apply(&self, suggestion: ProductSuggestion) -> Result<Decision> {
+        if suggestion.suggestion_type == LIFECYCLE_STAGE {
+            return self.apply_stage(suggestion).await;
+        }
+
+        let product = self.products.get_product(suggestion.product_id).await?;
+        if product.is_none_or(|product| is_stale(&suggestion, &product)) {
+            self.decide(suggestion, SuggestionStatus::Expired).await?;
+            return Ok(Decision::Expired);
+        }
+
+        let request = update_request(&suggestion.suggestion_type, &suggestion.suggested_value)?;
+        self.products.update_product(suggestion.product_id, request).await?;
+        Ok(Decision::Accepted(Box::new(self.decide(suggestion, SuggestionStatus::Accepted).await?)))
+    }
+
+    /// Move the product to the suggested stage, unless its stage changed
+    /// since the suggestion was made
+    async fn apply_stage(&self, suggestion: ProductSuggestion) -> Result<Decision> {
+        let Some(lifecycle) = &self.lifecycle else {
+            return Err(Error::new(
+                ErrorCode::ValidationFailed,
+                format!("Suggestions of type {} cannot be applied", LIFECYCLE_STAGE),
+            ));
+        };
+        let stage = suggestion.suggested_value["stage"]
+            .as_str()
+            .and_then(LifecycleStage::parse)
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorCode::ValidationFailed,
+                    format!("Suggested value {} is not a valid {}", suggestion.suggested_value, LIFECYCLE_STAGE),
+                )
+            })?;
+
+        let tenant_id = self.tenant_context.tenant_id;
+        let candidate = lifecycle
+            .lifecycle_candidates(tenant_id, Some(suggestion.product_id))
+            .await?
+            .into_iter()
+            .next();
+        if candidate.is_none_or(|candidate| suggestion.current_value != candidate.stage().as_str()) {
+            self.decide(suggestion, SuggestionStatus::Expired).await?;
+            return Ok(Decision::Expired);
+        }
+
+        lifecycle
+            .record_stage_change(
+                tenant_id,
+                &StageChange {
+                    product_id: suggestion.product_id,
+                    to: stage,
+                    source: StageChangeSource::Suggestion,
+                    rule: suggestion.suggested_value["rule"].as_str().map(str::to_string),
+                    evidence: Some(suggestion.suggested_value["evidence"].clone()).filter(|evidence| !evidence.is_null()),
+                    changed_by: self.tenant_context.user_id,
+                    changed_at: Utc::now(),
+                },
+            )
+            .await?;
+        Ok(Decision::Accepted(Box::new(self.decide(suggestion, SuggestionStatus::Accepted).await?)))
+    }
+}
+
+#[async_trait]
+impl ProductSuggestionService for DefaultProductSuggestionService {
+    async fn list_pending(&self, filter: SuggestionFilter) -> Result<Vec<ProductSuggestion>> {
+        let pending = self.repository.pending_suggestions(self.tenant_context.tenant_id, &filter).await?;
+
+        let mut products: HashMap<Uuid, Option<Product>> = HashMap::new();
+        let mut current = Vec::with_capacity(pending.len());
+        for suggestion in pending {
+            if let Entry::Vacant(entry) = products.entry(suggestion.product_id) {
+                entry.insert(self.products.get_product(suggestion.product_id).await?);
+            }
+            match &products[&suggestion.product_id] {
+                Some(product) if !is_stale(&suggestion, product) => current.push(suggestion),
+                _ => {
+                    self.decide(suggestion, SuggestionStatus::Expired).await?;
+                }
+            }
+        }
+        Ok(current)
+    }
+
+    async fn accept(&self, id: Uuid) -> Result<ProductSuggestion> {
+        let suggestion = self.pending(id).await?;
+        match self.apply(suggestion).await? {
+            Decision::Accepted(suggestion) => Ok(*suggestion),
+            Decision::Expired => Err(Error::new(
+                ErrorCode::ConflictError,
+                "The product changed since the suggestion was made; the suggestion has expired",
+            )),
+        }
+    }
+
+    async fn reject(&self, id: Uuid, reason: String) -> Result<ProductSuggestion> {
+        let reason = reason.trim();
+        if reason.is_empty() {
+            return Err(Error::new(ErrorCode::ValidationFailed, "A reason is required to reject a suggestion"));
+        }
+        let mut suggestion = self.pending(id).await?;
+        suggestion.rejection_reason = Some(reason.to_string());
+        self.decide(suggestion, SuggestionStatus::Rejected).await
+    }
+
+    async fn bulk_accept(&self, filter: SuggestionFilter) -> Result<BulkAcceptOutcome> {
+        let pending = self.repository.pending_suggestions(self.tenant_context.tenant_id, &filter).await?;
+
+        let mut outcome = BulkAcceptOutcome::default();
+        for suggestion in pending {
+            let suggestion_id = suggestion.id;
+            match self.apply(suggestion).await {
+                Ok(Decision::Accepted(_)) => outcome.accepted.push(suggestion_id),
+                Ok(Decision::Expired) => outcome.expired.push(suggestion_id),
+                Err(e) => outcome.failed.push(SuggestionFailure {
+                    suggestion_id,
+                    message: e.message.clone(),
+                }),
+            }
+        }
+        Ok(outcome)
+    }
+
+    async fn auto_apply_rules(&self) -> Result<Vec<AutoApplyRule>> {
+        self.repository.auto_apply_rules(self.tenant_context.tenant_id).await
+    }
+
+    async fn set_auto_apply_rules(&self, rules: Vec<AutoApplyRule>) -> Result<Vec<AutoApplyRule>> {
+        let mut types = HashSet::new();
+        for rule in &rules {
+            validate_rule(rule)?;
+            if !types.insert(rule.suggestion_type.as_str()) {
+                return Err(Error::new(
+                    ErrorCode::ValidationFailed,
+                    format!("More than one rule for {}", rule.suggestion_type),
+                ));
+            }
+        }
+
+        let tenant_id = self.tenant_context.tenant_id;
+        self.repository
+            .replace_auto_apply_rules(tenant_id, &rules, self.tenant_context.user_id)
+            .await?;
+        Ok(rules)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::product::service::OptimizationSuggestion;
+    use chrono::Duration;
+    use serde_json::Value;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct MemoryRepository {
+        suggestions: Mutex<HashMap<Uuid, ProductSuggestion>>,
+        rules: Mutex<Vec<AutoApplyRule>>,
+    }
+
+    impl MemoryRepository {
+        fn status(&self, id: Uuid) -> SuggestionStatus {
+            self.suggestions.lock().unwrap()[&id].status
+        }
+    }
+
+    #[async_trait]
+    impl ProductSuggestionRepository for MemoryRepository {
+        async fn auto_apply_rules(&self, _tenant_id: Uuid) -> Result<Vec<AutoApplyRule>> {
+            Ok(self.rules.lock().unwrap().clone())
+        }
+
+        async fn replace_auto_apply_rules(&self, _tenant_id: Uuid, rules: &[AutoApplyRule], _updated_by: Uuid) -> Result<()> {
+            *self.rules.lock().unwrap() = rules.to_vec();
+            Ok(())
+        }
+
+        async fn record_suggestions(&self, _tenant_id: Uuid, product_id: Uuid, suggestions: &[ProductSuggestion]) -> Result<()> {
+            let mut stored = self.suggestions.lock().unwrap();
+            for suggestion in stored.values_mut() {
+                if suggestion.product_id == product_id
+                    && suggestion.status == SuggestionStatus::Pending
+                    && suggestion.suggestion_type != LIFECYCLE_STAGE
+                {
+                    suggestion.status = SuggestionStatus::Expired;
+                }
+            }
+            stored.extend(suggestions.iter().map(|s| (s.id, s.clone())));
+            Ok(())
+        }
+
+        async fn get_suggestion(&self, _tenant_id: Uuid, id: Uuid) -> Result<Option<ProductSuggestion>> {
+            Ok(self.suggestions.lock().unwrap().get(&id).cloned())
+        }
+
+        async fn pending_suggestions(&self, _tenant_id: Uuid, filter: &SuggestionFilter) -> Result<Vec<ProductSuggestion>> {
+            let mut pending: Vec<_> = self
+                .suggestions
+                .lock()
+                .unwrap()
+                .values()
+                .filter(|s| s.status == SuggestionStatus::Pending && filter.matches(s))
+                .cloned()
+                .collect();
+            pending.sort_by_key(|s| s.created_at);
+            Ok(pending)
+        }
+
+        async fn decide_suggestion(&self, suggestion: &ProductSuggestion) -> Result<bool> {
+            let mut stored = self.suggestions.lock().unwrap();
+            match stored.get_mut(&suggestion.id) {
+                Some(existing) if existing.status == SuggestionStatus::Pending => {
+                    *existing = suggestion.clone();
+                    Ok(true)
+                }
+                _ => Ok(false),
+            }
+        }
+    }
+
+    /// Updates the way the product service does: validated by `apply_update`
+    #[derive(Default)]
+    struct MemoryProducts {
+        products: Mutex<HashMap<Uuid, Product>>,
+    }
+
+    impl MemoryProducts {
+        fn add(&self, tenant_id: Uuid, base_price: i64) -> Product {
+            let mut product = Product::new(tenant_id, format!("SKU-{}", base_price), "Widget".to_string(), Uuid::new_v4());
+            product.base_price = base_price;
+            product.reorder_point = Some(10);
+            self.products.lock().unwrap().insert(product.id, product.clone());
+            product
+        }
+
+        fn product(&self, id: Uuid) -> Product {
+            self.products.lock().unwrap()[&id].clone()
+        }
+    }
+
+    #[async_trait]
+    impl SuggestionTarget for MemoryProducts {
+        async fn get_product(&self, product_id: Uuid) -> Result<Option<Product>> {
+            Ok(self.products.lock().unwrap().get(&product_id).cloned())
+        }
+
+        async fn update_product(&self, product_id: Uuid, request: UpdateProductRequest) -> Result<Product> {
+            let mut product = self.product(product_id);
+            product.apply_update(request)?;
+            product.updated_at += Duration::milliseconds(1);
+            self.products.lock().unwrap().insert(product_id, product.clone());
+            Ok(product)
+        }
+    }
+
+    struct Fixture {
+        repository: Arc<MemoryRepository>,
+        products: Arc<MemoryProducts>,
+        service: DefaultProductSuggestionService,
+        tenant_id: Uuid,
+    }
+
+    fn fixture() -> Fixture {
+        let repository = Arc::new(MemoryRepository::default());
+        let products = Arc::new(MemoryProducts::default());
+        let context = TenantContext::new(Uuid::new_v4(), "acme".to_string(), Uuid::new_v4());
+        let tenant_id = context.tenant_id;
+        let service = DefaultProductSuggestionService::new(repository.clone(), products.clone(), context);
+        Fixture {
+            repository,
+            products,
+            service,
+            tenant_id,
+        }
+    }
+
+    impl Fixture {
+        /// Record a pending suggestion for the product as it is now
+        async fn suggest(&self, product_id: Uuid, suggestion_type: &str, value: i64, confidence: f64) -> Uuid {
+            let suggestion = OptimizationSuggestion {
+                suggestion_type: suggestion_type.to_string(),
+                description: "Demand is steady".to_string(),
+                suggested_value: Value::from(value),
+                confidence,
+                expected_impact: String::new(),
+            };
+            let mut record = ProductSuggestion::new(&self.products.product(product_id), &suggestion, SuggestionSource::Job, Utc::now());
+            // Keep listing order stable
+            record.created_at += Duration::microseconds(self.repository.suggestions.lock().unwrap().len() as i64);
+            self.repository.suggestions.lock().unwrap().insert(record.id, record.clone());
+            record.id
+        }
+    }
+
+    #[tokio::test]
+    async fn test_accept_applies_the_value_through_the_product_update() {
+        let f = fixture();
+        let product = f.products.add(f.tenant_id, 1999);
+        let id = f.suggest(product.id, PRICE_OPTIMIZATION, 1799, 0.8).await;
+
+        let accepted = f.service.accept(id).await.unwrap();
+        assert_eq!(accepted.status, SuggestionStatus::Accepted);
+        assert!(accepted.decided_by.is_some() && accepted.decided_at.is_some());
+        assert_eq!(f.products.product(product.id).base_price, 1799);
+
+        let again = f.service.accept(id).await.unwrap_err();
+        assert_eq!(again.code, ErrorCode::ConflictError);
+    }
+
+    #[tokio::test]
+    async fn test_accept_runs_the_update_validations() {
+        let f = fixture();
+        let product = f.products.add(f.tenant_id, 1999);
+        let id = f.suggest(product.id, PRICE_OPTIMIZATION, -100, 0.99).await;
+
+        let error = f.service.accept(id).await.unwrap_err();
+        assert_eq!(error.code, ErrorCode::ValidationFailed);
+        assert_eq!(f.products.product(product.id).base_price, 1999);
+        assert_eq!(f.repository.status(id), SuggestionStatus::Pending);
+    }
+
+    #[tokio::test]
+    async fn test_stale_suggestions_are_expired_instead_of_applied() {
+        let f = fixture();
+        let product = f.products.add(f.tenant_id, 1999);
+        let stale = f.suggest(product.id, PRICE_OPTIMIZATION, 1799, 0.99).await;
+        let listed = f.suggest(product.id, REORDER_POINT_OPTIMIZATION, 25, 0.7).await;
+
+        // Someone edits the product after the suggestions were made
+        f.products
+            .update_product(product.id, UpdateProductRequest { base_price: Some(2199), ..Default::default() })
+            .await
+            .unwrap();
+
+        let error = f.service.accept(stale).await.unwrap_err();
+        assert_eq!(error.code, ErrorCode::ConflictError);
+        assert_eq!(f.repository.status(stale), SuggestionStatus::Expired);
+        assert_eq!(f.products.product(product.id).base_price, 2199);
+
+        assert!(f.service.list_pending(SuggestionFilter::default()).await.unwrap().is_empty());
+        assert_eq!(f.repository.status(listed), SuggestionStatus::Expired);
+    }
+
+    #[tokio::test]
+    async fn test_bulk_accept_by_filter() {
+        let f = fixture();
+        let (a, b) = (f.products.add(f.tenant_id, 1000), f.products.add(f.tenant_id, 2000));
+        let a_price = f.suggest(a.id, PRICE_OPTIMIZATION, 1100, 0.9).await;
+        let a_second = f.suggest(a.id, PRICE_OPTIMIZATION, 1150, 0.95).await;
+        let b_price = f.suggest(b.id, PRICE_OPTIMIZATION, -1, 0.9).await;
+        let b_low = f.suggest(b.id, PRICE_OPTIMIZATION, 2100, 0.3).await;
+        let b_reorder = f.suggest(b.id, REORDER_POINT_OPTIMIZATION, 30, 0.9).await;
+
+        let filter = SuggestionFilter {
+            suggestion_type: Some(PRICE_OPTIMIZATION.to_string()),
+            min_confidence: Some(0.5),
+            ..Default::default()
+        };
+        let outcome = f.service.bulk_accept(filter).await.unwrap();
+        assert_eq!(outcome.accepted, vec![a_price]);
+        // Made for the price the first acceptance replaced
+        assert_eq!(outcome.expired, vec![a_second]);
+        assert_eq!(outcome.failed.len(), 1);
+        assert_eq!(outcome.failed[0].suggestion_id, b_price);
+
+        assert_eq!(f.products.product(a.id).base_price, 1100);
+        assert_eq!(f.products.product(b.id).base_price, 2000);
+        for untouched in [b_price, b_low, b_reorder] {
+            assert_eq!(f.repository.status(untouched), SuggestionStatus::Pending);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reject_needs_a_reason() {
+        let f = fixture();
+        let product = f.products.add(f.tenant_id, 1999);
+        let id = f.suggest(product.id, PRICE_OPTIMIZATION, 999, 0.6).await;
+
+        let error = f.service.reject(id, "  ".to_string()).await.unwrap_err();
+        assert_eq!(error.code, ErrorCode::ValidationFailed);
+
+        let rejected = f.service.reject(id, " Price is contractual ".to_string()).await.unwrap();
+        assert_eq!(rejected.status, SuggestionStatus::Rejected);
+        assert_eq!(rejected.rejection_reason.as_deref(), Some("Price is contractual"));
+        assert_eq!(f.products.product(product.id).base_price, 1999);
+    }
+
+    #[tokio::test]
+    async fn test_auto_apply_rules_are_validated() {
+        let f = fixture();
+        let rule = |suggestion_type: &str, min_confidence: f64| AutoApplyRule {
+            suggestion_type: suggestion_type.to_string(),
+            min_confidence,
+        };
+
+        for rules in [
+            vec![rule(PRICE_OPTIMIZATION, 0.0)],
+            vec![rule("bundle_optimization", 0.9)],
+            vec![rule(PRICE_OPTIMIZATION, 0.9), rule(PRICE_OPTIMIZATION, 0.95)],
+        ] {
+            let error = f.service.set_auto_apply_rules(rules).await.unwrap_err();
+            assert_eq!(error.code, ErrorCode::ValidationFailed);
+        }
+        assert!(f.service.auto_apply_rules().await.unwrap().is_empty());
+
+        f.service.set_auto_apply_rules(vec![rule(REORDER_POINT_OPTIMIZATION, 0.95)]).await.unwrap();
+        assert_eq!(f.service.auto_apply_rules().await.unwrap(), vec![rule(REORDER_POINT_OPTIMIZATION, 0.95)]);
+    }
+}
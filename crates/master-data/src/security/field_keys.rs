@@ -0,0 +1,275 @@
+//! Versioned keys for fields encrypted at rest
+//!
+//! Every [`EncryptedField`] records the id of the key that sealed it in
+//! `key_id`, so keys can be rotated: new values are sealed with the current
+//! key, older keys stay in the ring until everything they sealed has been
+//! re-encrypted. Unlike [`super::EncryptionService`], the derived key depends
+//! only on the key, the tenant and the field name, so a value stored by one
+//! user can be read back by any other user of the tenant.
+
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+    Aes256Gcm, Key, Nonce,
+};
+use base64::{engine::general_purpose, Engine as _};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use uuid::Uuid;
+
+use super::encryption::{EncryptedField, EncryptionAlgorithm, EncryptionContext, FieldEncryption};
+use crate::error::{MasterDataError, Result};
+
+/// Master keys by id, one of them current
+#[derive(Clone)]
+pub struct KeyRing {
+    keys: BTreeMap<String, Vec<u8>>,
+    current: String,
+}
+
+impl std::fmt::Debug for KeyRing {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KeyRing")
+            .field("key_ids", &self.keys.keys().collect::<Vec<_>>())
+            .field("current", &self.current)
+            .finish()
+    }
+}
+
+impl KeyRing {
+    /// Every key must be 32 bytes and `current` one of the ids
+    pub fn new(keys: BTreeMap<String, Vec<u8>>, current: &str) -> Result<Self> {
+        if let Some((id, _)) = keys.iter().find(|(_, key)| key.len() != 32) {
+            return Err(MasterDataError::ValidationError {
+                field: "encryption_keys".to_string(),
+                message: format!("Key {} must be exactly 32 bytes (256 bits)", id),
+            });
+        }
+        if !keys.contains_key(current) {
+            return Err(MasterDataError::ValidationError {
+                field: "current_key_id".to_string(),
+                message: format!("Current key {} is not in the key ring", current),
+            });
+        }
+        Ok(Self {
+            keys,
+            current: current.to_string(),
+        })
+    }
+
+    pub fn current_key_id(&self) -> &str {
+        &self.current
+    }
+
+    pub fn contains(&self, key_id: &str) -> bool {
+        self.keys.contains_key(key_id)
+    }
+}
+
+/// [`FieldEncryption`] over a [`KeyRing`], for values stored in the database
+#[derive(Debug, Clone)]
+pub struct VersionedFieldEncryption {
+    ring: KeyRing,
+}
+
+fn decryption_failed(field_name: &str, key_id: &str, reason: impl Into<String>) -> MasterDataError {
+    MasterDataError::FieldDecryptionFailed {
+        field: field_name.to_string(),
+        key_id: key_id.to_string(),
+        reason: reason.into(),
+    }
+}
+
+impl VersionedFieldEncryption {
+    pub fn new(ring: KeyRing) -> Self {
+        Self { ring }
+    }
+
+    pub fn current_key_id(&self) -> &str {
+        self.ring.current_key_id()
+    }
+
+    fn field_key(&self, key_id: &str, tenant_id: Uuid, field_name: &str) -> Option<Vec<u8>> {
+        let master_key = self.ring.keys.get(key_id)?;
+        let mut hasher = Sha256::new();
+        hasher.update(master_key);
+        hasher.update(tenant_id.as_bytes());
+        hasher.update(field_name.as_bytes());
+        Some(hasher.finalize().to_vec())
+    }
+
+    fn context_hash(tenant_id: Uuid, field_name: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(format!("{}:{}", tenant_id, field_name).as_bytes());
+        general_purpose::STANDARD.encode(hasher.finalize())
+    }
+
+    fn integrity_hash(field_key: &[u8], encrypted_data: &[u8], nonce: &[u8]) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(encrypted_data);
+        hasher.update(nonce);
+        hasher.update(field_key);
+        general_purpose::STANDARD.encode(hasher.finalize())
+    }
+}
+
+#[async_trait::async_trait]
+impl FieldEncryption for VersionedFieldEncryption {
+    async fn encrypt_field(&self, value: &str, field_name: &str, context: &EncryptionContext) -> Result<EncryptedField> {
+        let key_id = self.ring.current_key_id();
+        let field_key = self
+            .field_key(key_id, context.tenant_id, field_name)
+            .expect("the current key is always in the ring");
+        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&field_key));
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+
+        let encrypted_data = cipher
+            .encrypt(&nonce, value.as_bytes())
+            .map_err(|e| MasterDataError::ValidationError {
+                field: field_name.to_string(),
+                message: format!("Encryption failed: {}", e),
+            })?;
+
+        Ok(EncryptedField {
+            encrypted_data: general_purpose::STANDARD.encode(&encrypted_data),
+            nonce: general_purpose::STANDARD.encode(nonce),
+            algorithm: EncryptionAlgorithm::Aes256Gcm,
+            key_id: key_id.to_string(),
+            field_name: field_name.to_string(),
+            encrypted_at: chrono::Utc::now(),
+            integrity_hash: Self::integrity_hash(&field_key, &encrypted_data, &nonce),
+            context_hash: Self::context_hash(context.tenant_id, field_name),
+        })
+    }
+
+    async fn decrypt_field(&self, encrypted_field: &EncryptedField, context: &EncryptionContext) -> Result<String> {
+        let field_name = &encrypted_field.field_name;
+        let key_id = &encrypted_field.key_id;
+
+        if encrypted_field.context_hash != Self::context_hash(context.tenant_id, field_name) {
+            return Err(decryption_failed(field_name, key_id, "sealed for another tenant or field"));
+        }
+        let field_key = self
+            .field_key(key_id, context.tenant_id, field_name)
+            .ok_or_else(|| decryption_failed(field_name, key_id, "key is not in the key ring"))?;
+
+        let encrypted_data = general_purpose::STANDARD
+            .decode(&encrypted_field.encrypted_data)
+            .map_err(|e| decryption_failed(field_name, key_id, format!("invalid ciphertext encoding: {}", e)))?;
+        let nonce_bytes = general_purpose::STANDARD
+            .decode(&encrypted_field.nonce)
+            .map_err(|e| decryption_failed(field_name, key_id, format!("invalid nonce encoding: {}", e)))?;
+        if nonce_bytes.len() != 12 {
+            return Err(decryption_failed(field_name, key_id, "invalid nonce length"));
+        }
+        if Self::integrity_hash(&field_key, &encrypted_data, &nonce_bytes) != encrypted_field.integrity_hash {
+            return Err(decryption_failed(field_name, key_id, "integrity check failed"));
+        }
+
+        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&field_key));
+        let decrypted = cipher
+            .decrypt(Nonce::from_slice(&nonce_bytes), encrypted_data.as_ref())
+            .map_err(|_| decryption_failed(field_name, key_id, "authentication failed"))?;
+
+        String::from_utf8(decrypted).map_err(|_| decryption_failed(field_name, key_id, "plaintext is not UTF-8"))
+    }
+
+    async fn encrypt_fields(
+        &self,
+        fields: &HashMap<String, String>,
+        context: &EncryptionContext,
+    ) -> Result<HashMap<String, EncryptedField>> {
+        let mut encrypted_fields = HashMap::new();
+        for (field_name, value) in fields {
+            encrypted_fields.insert(field_name.clone(), self.encrypt_field(value, field_name, context).await?);
+        }
+        Ok(encrypted_fields)
+    }
+
+    async fn decrypt_fields(
+        &self,
+        encrypted_fields: &HashMap<String, EncryptedField>,
+        context: &EncryptionContext,
+    ) -> Result<HashMap<String, String>> {
+        let mut decrypted_fields = HashMap::new();
+        for (field_name, encrypted_field) in encrypted_fields {
+            decrypted_fields.insert(field_name.clone(), self.decrypt_field(encrypted_field, context).await?);
+        }
+        Ok(decrypted_fields)
+    }
+
+    /// Keys rotate through configuration: add a key, make it current and let
+    /// the backfill re-encrypt what older keys sealed
+    async fn rotate_encryption_keys(&self, _context: &EncryptionContext) -> Result<()> {
+        Ok(())
+    }
+
+    async fn validate_encryption(&self, encrypted_field: &EncryptedField) -> Result<bool> {
+        Ok(self.ring.contains(&encrypted_field.key_id)
+            && !encrypted_field.integrity_hash.is_empty()
+            && general_purpose::STANDARD.decode(&encrypted_field.encrypted_data).is_ok()
+            && general_purpose::STANDARD.decode(&encrypted_field.nonce).is_ok())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::security::encryption::{ComplianceLevel, DataClassification, EncryptionOperation};
+
+    fn ring(keys: &[(&str, u8)], current: &str) -> KeyRing {
+        KeyRing::new(keys.iter().map(|(id, byte)| (id.to_string(), vec![*byte; 32])).collect(), current).unwrap()
+    }
+
+    fn context(tenant_id: Uuid, operation: EncryptionOperation) -> EncryptionContext {
+        EncryptionContext {
+            tenant_id,
+            user_id: Uuid::new_v4(),
+            operation,
+            compliance_level: ComplianceLevel::High,
+            data_classification: DataClassification::Restricted,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_values_survive_rotation_and_other_readers() {
+        let tenant_id = Uuid::new_v4();
+        let v1 = VersionedFieldEncryption::new(ring(&[("v1", 1)], "v1"));
+        let sealed = v1
+            .encrypt_field("DE89370400440532013000", "bank_accounts", &context(tenant_id, EncryptionOperation::Create))
+            .await
+            .unwrap();
+        assert_eq!(sealed.key_id, "v1");
+
+        let rotated = VersionedFieldEncryption::new(ring(&[("v1", 1), ("v2", 2)], "v2"));
+        let read = context(tenant_id, EncryptionOperation::Read);
+        assert_eq!(rotated.decrypt_field(&sealed, &read).await.unwrap(), "DE89370400440532013000");
+        assert_eq!(rotated.encrypt_field("x", "bank_accounts", &read).await.unwrap().key_id, "v2");
+    }
+
+    #[tokio::test]
+    async fn test_decryption_errors_name_field_and_key() {
+        let tenant_id = Uuid::new_v4();
+        let v2 = VersionedFieldEncryption::new(ring(&[("v2", 2)], "v2"));
+        let mut sealed = v2
+            .encrypt_field("GB123456789", "tax_numbers", &context(tenant_id, EncryptionOperation::Create))
+            .await
+            .unwrap();
+
+        let retired = VersionedFieldEncryption::new(ring(&[("v3", 3)], "v3"));
+        let err = retired.decrypt_field(&sealed, &context(tenant_id, EncryptionOperation::Read)).await.unwrap_err();
+        assert_eq!(err.to_string(), "Cannot decrypt tax_numbers sealed with key v2: key is not in the key ring");
+
+        let other_tenant = v2.decrypt_field(&sealed, &context(Uuid::new_v4(), EncryptionOperation::Read)).await;
+        assert!(matches!(other_tenant, Err(MasterDataError::FieldDecryptionFailed { .. })));
+
+        sealed.encrypted_data = general_purpose::STANDARD.encode(b"tampered");
+        let err = v2.decrypt_field(&sealed, &context(tenant_id, EncryptionOperation::Read)).await.unwrap_err();
+        assert!(err.to_string().contains("tax_numbers") && err.to_string().contains("v2"));
+    }
+
+    #[test]
+    fn test_key_ring_rejects_bad_keys() {
+        assert!(KeyRing::new([("v1".to_string(), vec![0; 16])].into(), "v1").is_err());
+        assert!(KeyRing::new([("v1".to_string(), vec![0; 32])].into(), "v2").is_err());
+    }
+}
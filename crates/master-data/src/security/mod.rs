@@ -1,4 +1,5 @@
 pub mod encryption;
+pub mod field_keys;
 pub mod access_control;
 pub mod audit;
 pub mod data_masking;
@@ -6,6 +7,7 @@ pub mod compliance;
 
 // Re-exports for public API
 pub use encryption::{FieldEncryption, EncryptionService, EncryptedField, EncryptionContext};
+pub use field_keys::{KeyRing, VersionedFieldEncryption};
 pub use access_control::{AccessControl, Permission, Role, AccessControlService};
 pub use audit::{AuditLogger, AuditEvent, AuditTrail, SecurityAuditService};
 pub use data_masking::{DataMasking, MaskingPolicy, PrivacyControls};
@@ -0,0 +1,24 @@
+//! Child spans of service methods, so a slow call shows whether the time went
+//! to the database or to an external engine
+//!
+//! Service methods are `#[tracing::instrument]`ed with their tenant and entity
+//! ids and timed against their latency budget (see `erp_core::latency`); the
+//! calls they make to their repository and engines are wrapped in these spans:
+//!
+//! ```ignore
+//! let customers = self.repository.search_customers(&criteria)
+//!     .instrument(spans::repository("search_customers"))
+//!     .await?;
+//! ```
+
+use tracing::Span;
+
+/// Span of a repository call
+pub(crate) fn repository(operation: &'static str) -> Span {
+    tracing::info_span!("repository", operation)
+}
+
+/// Span of a call to an external engine (AI, pricing, quality)
+pub(crate) fn engine(engine: &'static str, operation: &'static str) -> Span {
+    tracing::info_span!("engine", engine, operation)
+}
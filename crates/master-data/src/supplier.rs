@@ -12,8 +12,10 @@
 //! - **Performance Tracking**: Supplier ratings and KPIs
 //! - **Payment Terms**: Flexible payment configurations
 //! - **Category Management**: Supplier categorization and classification
+//! - **Product Catalog**: Supplier price lists with quantity breaks and validity windows
 
 pub mod model;
+pub mod catalog;
 pub mod repository;
 pub mod service;
 pub mod analytics;
@@ -22,6 +24,7 @@ pub mod analytics;
 pub mod handlers;
 
 pub use model::*;
+pub use catalog::*;
 pub use repository::*;
 pub use service::*;
 pub use analytics::*;
\ No newline at end of file
@@ -0,0 +1,343 @@
+//! Supplier product catalog and price lists
+//!
+//! A catalog entry describes what a supplier charges for one of our products:
+//! their SKU, currency, quantity price breaks, ordering constraints and the
+//! window in which the price list is valid. Procurement picks the best valid
+//! entry for a requested quantity instead of relying on a flat cost price.
+
+use chrono::{DateTime, Utc};
+use erp_core::error::{Error, ErrorCode, Result};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Unit price that applies from `min_quantity` upwards
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PriceBreak {
+    pub min_quantity: i32,
+    /// Unit price in cents
+    pub unit_price: i64,
+}
+
+/// A supplier's price list entry for a single product
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SupplierCatalogEntry {
+    pub id: Uuid,
+    pub tenant_id: Uuid,
+    pub supplier_id: Uuid,
+    pub product_id: Uuid,
+
+    pub supplier_sku: Option<String>,
+    pub currency: String,
+    /// Sorted ascending by `min_quantity`; the first break starts at 1
+    pub price_breaks: Vec<PriceBreak>,
+
+    // Ordering constraints
+    pub min_order_quantity: i32,
+    pub pack_size: i32,
+    pub lead_time_days: i32,
+
+    // Validity window, `valid_to` is exclusive
+    pub valid_from: DateTime<Utc>,
+    pub valid_to: Option<DateTime<Utc>>,
+    pub is_active: bool,
+
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub created_by: Uuid,
+    pub updated_by: Uuid,
+}
+
+impl SupplierCatalogEntry {
+    pub fn is_valid_at(&self, at: DateTime<Utc>) -> bool {
+        self.is_active && self.valid_from <= at && self.valid_to.is_none_or(|to| at < to)
+    }
+
+    /// Smallest orderable quantity covering `quantity`, honouring MOQ and pack size
+    pub fn orderable_quantity(&self, quantity: i32) -> i32 {
+        let quantity = quantity.max(self.min_order_quantity).max(1);
+        let pack = self.pack_size.max(1);
+        (quantity + pack - 1) / pack * pack
+    }
+
+    /// Unit price of the highest price break reached by `quantity`
+    pub fn unit_price_for(&self, quantity: i32) -> Option<i64> {
+        self.price_breaks
+            .iter()
+            .filter(|b| b.min_quantity <= quantity)
+            .max_by_key(|b| b.min_quantity)
+            .map(|b| b.unit_price)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateCatalogEntryRequest {
+    pub product_id: Uuid,
+    pub supplier_sku: Option<String>,
+    pub currency: String,
+    pub price_breaks: Vec<PriceBreak>,
+    pub min_order_quantity: Option<i32>,
+    pub pack_size: Option<i32>,
+    pub lead_time_days: i32,
+    pub valid_from: Option<DateTime<Utc>>,
+    pub valid_to: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdateCatalogEntryRequest {
+    pub supplier_sku: Option<String>,
+    pub currency: Option<String>,
+    pub price_breaks: Option<Vec<PriceBreak>>,
+    pub min_order_quantity: Option<i32>,
+    pub pack_size: Option<i32>,
+    pub lead_time_days: Option<i32>,
+    pub valid_from: Option<DateTime<Utc>>,
+    pub valid_to: Option<DateTime<Utc>>,
+    pub is_active: Option<bool>,
+}
+
+/// Parameters for choosing a catalog entry
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CatalogQuoteRequest {
+    pub quantity: i32,
+    /// Point in time the price must be valid at (defaults to now)
+    pub as_of: Option<DateTime<Utc>>,
+    /// Entries with a longer lead time are only used when nothing faster exists
+    pub max_lead_time_days: Option<i32>,
+    /// Use this supplier even if another one is cheaper
+    pub supplier_override: Option<Uuid>,
+}
+
+/// Price resolved from a catalog entry for a given quantity
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CatalogQuote {
+    pub entry_id: Uuid,
+    pub supplier_id: Uuid,
+    pub product_id: Uuid,
+    pub supplier_sku: Option<String>,
+    pub currency: String,
+    pub requested_quantity: i32,
+    /// Quantity after applying MOQ and pack size
+    pub order_quantity: i32,
+    pub unit_price: i64,
+    pub total_cost: i64,
+    pub lead_time_days: i32,
+    pub valid_to: Option<DateTime<Utc>>,
+    pub meets_lead_time: bool,
+}
+
+impl CatalogQuote {
+    /// Whether the underlying price list still applies at `at`
+    pub fn is_valid_at(&self, at: DateTime<Utc>) -> bool {
+        self.valid_to.is_none_or(|to| at < to)
+    }
+}
+
+/// Quote a single entry, `None` if it is not usable at `at`
+pub fn quote_entry(entry: &SupplierCatalogEntry, request: &CatalogQuoteRequest, at: DateTime<Utc>) -> Option<CatalogQuote> {
+    if request.quantity <= 0 || !entry.is_valid_at(at) {
+        return None;
+    }
+
+    let order_quantity = entry.orderable_quantity(request.quantity);
+    let unit_price = entry.unit_price_for(order_quantity)?;
+
+    Some(CatalogQuote {
+        entry_id: entry.id,
+        supplier_id: entry.supplier_id,
+        product_id: entry.product_id,
+        supplier_sku: entry.supplier_sku.clone(),
+        currency: entry.currency.clone(),
+        requested_quantity: request.quantity,
+        order_quantity,
+        unit_price,
+        total_cost: unit_price * order_quantity as i64,
+        lead_time_days: entry.lead_time_days,
+        valid_to: entry.valid_to,
+        meets_lead_time: request.max_lead_time_days.is_none_or(|max| entry.lead_time_days <= max),
+    })
+}
+
+/// Pick the best valid entry for the request.
+///
+/// With a supplier override only that supplier's entries are considered.
+/// Entries meeting the lead-time limit win; among them the lowest total cost
+/// (after MOQ and pack rounding) is chosen, ties going to the shorter lead time.
+/// When no entry meets the limit the fastest one is returned instead.
+pub fn select_best_entry(
+    entries: &[SupplierCatalogEntry],
+    request: &CatalogQuoteRequest,
+) -> Option<CatalogQuote> {
+    let at = request.as_of.unwrap_or_else(Utc::now);
+
+    entries
+        .iter()
+        .filter(|e| request.supplier_override.is_none_or(|s| e.supplier_id == s))
+        .filter_map(|e| quote_entry(e, request, at))
+        .min_by(|a, b| {
+            b.meets_lead_time
+                .cmp(&a.meets_lead_time)
+                .then_with(|| {
+                    if a.meets_lead_time {
+                        a.total_cost.cmp(&b.total_cost).then(a.lead_time_days.cmp(&b.lead_time_days))
+                    } else {
+                        a.lead_time_days.cmp(&b.lead_time_days).then(a.total_cost.cmp(&b.total_cost))
+                    }
+                })
+        })
+}
+
+/// Check price breaks are usable and return them sorted
+pub fn normalize_price_breaks(mut breaks: Vec<PriceBreak>) -> Result<Vec<PriceBreak>> {
+    if breaks.is_empty() {
+        return Err(Error::new(ErrorCode::ValidationFailed, "At least one price break is required"));
+    }
+
+    breaks.sort_by_key(|b| b.min_quantity);
+
+    if breaks[0].min_quantity != 1 {
+        return Err(Error::new(ErrorCode::ValidationFailed, "The first price break must start at quantity 1"));
+    }
+
+    for pair in breaks.windows(2) {
+        if pair[0].min_quantity == pair[1].min_quantity {
+            return Err(Error::new(
+                ErrorCode::ValidationFailed,
+                format!("Duplicate price break at quantity {}", pair[0].min_quantity),
+            ));
+        }
+    }
+
+    if breaks.iter().any(|b| b.unit_price < 0) {
+        return Err(Error::new(ErrorCode::ValidationFailed, "Price break unit price cannot be negative"));
+    }
+
+    Ok(breaks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn entry(supplier_id: Uuid, breaks: &[(i32, i64)], lead_time_days: i32) -> SupplierCatalogEntry {
+        let now = Utc::now();
+        SupplierCatalogEntry {
+            id: Uuid::new_v4(),
+            tenant_id: Uuid::nil(),
+            supplier_id,
+            product_id: Uuid::nil(),
+            supplier_sku: None,
+            currency: "USD".to_string(),
+            price_breaks: breaks
+                .iter()
+                .map(|&(min_quantity, unit_price)| PriceBreak { min_quantity, unit_price })
+                .collect(),
+            min_order_quantity: 1,
+            pack_size: 1,
+            lead_time_days,
+            valid_from: now - Duration::days(30),
+            valid_to: None,
+            is_active: true,
+            created_at: now,
+            updated_at: now,
+            created_by: Uuid::nil(),
+            updated_by: Uuid::nil(),
+        }
+    }
+
+    fn request(quantity: i32) -> CatalogQuoteRequest {
+        CatalogQuoteRequest { quantity, ..Default::default() }
+    }
+
+    #[test]
+    fn test_exact_tier_boundary_uses_that_tier() {
+        let e = entry(Uuid::new_v4(), &[(1, 1000), (100, 900), (500, 800)], 5);
+
+        assert_eq!(quote_entry(&e, &request(99), Utc::now()).unwrap().unit_price, 1000);
+        assert_eq!(quote_entry(&e, &request(100), Utc::now()).unwrap().unit_price, 900);
+        assert_eq!(quote_entry(&e, &request(500), Utc::now()).unwrap().unit_price, 800);
+    }
+
+    #[test]
+    fn test_moq_and_pack_size_round_up_before_pricing() {
+        let mut e = entry(Uuid::new_v4(), &[(1, 1000), (100, 900)], 5);
+        e.min_order_quantity = 50;
+        e.pack_size = 24;
+
+        let quote = quote_entry(&e, &request(10), Utc::now()).unwrap();
+        assert_eq!(quote.order_quantity, 72);
+        assert_eq!(quote.total_cost, 72 * 1000);
+
+        // Rounding up to a full pack can reach the next tier
+        let quote = quote_entry(&e, &request(90), Utc::now()).unwrap();
+        assert_eq!(quote.order_quantity, 96);
+        assert_eq!(quote.unit_price, 1000);
+        let quote = quote_entry(&e, &request(97), Utc::now()).unwrap();
+        assert_eq!(quote.order_quantity, 120);
+        assert_eq!(quote.unit_price, 900);
+    }
+
+    #[test]
+    fn test_validity_window_expiring_mid_quote() {
+        let now = Utc::now();
+        let cheap_supplier = Uuid::new_v4();
+        let mut cheap = entry(cheap_supplier, &[(1, 500)], 5);
+        cheap.valid_to = Some(now + Duration::hours(1));
+        let regular = entry(Uuid::new_v4(), &[(1, 700)], 5);
+        let entries = vec![cheap, regular.clone()];
+
+        let quote = select_best_entry(&entries, &CatalogQuoteRequest { as_of: Some(now), ..request(10) }).unwrap();
+        assert_eq!(quote.supplier_id, cheap_supplier);
+        assert!(quote.is_valid_at(now));
+
+        // The price list lapses before the quote is acted on
+        let later = now + Duration::hours(2);
+        assert!(!quote.is_valid_at(later));
+        let requote = select_best_entry(&entries, &CatalogQuoteRequest { as_of: Some(later), ..request(10) }).unwrap();
+        assert_eq!(requote.supplier_id, regular.supplier_id);
+        assert_eq!(requote.unit_price, 700);
+
+        // valid_to is exclusive
+        let at_expiry = now + Duration::hours(1);
+        assert!(!entries[0].is_valid_at(at_expiry));
+    }
+
+    #[test]
+    fn test_lead_time_constraint_and_override() {
+        let fast = entry(Uuid::new_v4(), &[(1, 900)], 3);
+        let slow = entry(Uuid::new_v4(), &[(1, 600)], 30);
+        let entries = vec![fast.clone(), slow.clone()];
+
+        let cheapest = select_best_entry(&entries, &request(10)).unwrap();
+        assert_eq!(cheapest.supplier_id, slow.supplier_id);
+
+        let constrained = CatalogQuoteRequest { max_lead_time_days: Some(7), ..request(10) };
+        assert_eq!(select_best_entry(&entries, &constrained).unwrap().supplier_id, fast.supplier_id);
+
+        let none_fast_enough = CatalogQuoteRequest { max_lead_time_days: Some(1), ..request(10) };
+        let fallback = select_best_entry(&entries, &none_fast_enough).unwrap();
+        assert_eq!(fallback.supplier_id, fast.supplier_id);
+        assert!(!fallback.meets_lead_time);
+
+        let overridden = CatalogQuoteRequest { supplier_override: Some(fast.supplier_id), ..request(10) };
+        assert_eq!(select_best_entry(&entries, &overridden).unwrap().supplier_id, fast.supplier_id);
+    }
+
+    #[test]
+    fn test_normalize_price_breaks() {
+        let breaks = normalize_price_breaks(vec![
+            PriceBreak { min_quantity: 100, unit_price: 900 },
+            PriceBreak { min_quantity: 1, unit_price: 1000 },
+        ])
+        .unwrap();
+        assert_eq!(breaks[0].min_quantity, 1);
+
+        assert!(normalize_price_breaks(vec![]).is_err());
+        assert!(normalize_price_breaks(vec![PriceBreak { min_quantity: 10, unit_price: 1 }]).is_err());
+        assert!(normalize_price_breaks(vec![
+            PriceBreak { min_quantity: 1, unit_price: 1 },
+            PriceBreak { min_quantity: 1, unit_price: 2 },
+        ])
+        .is_err());
+    }
+}
@@ -3,7 +3,7 @@
 //! This module provides database operations for supplier management,
 //! including CRUD operations, search functionality, and performance tracking.
 
-use super::model::*;
+use super::{catalog::*, model::*};
 use async_trait::async_trait;
 use erp_core::{
     database::DatabasePool,
@@ -1004,4 +1004,192 @@ impl SupplierRepository for PostgresSupplierRepository {
             })
             .collect())
     }
-}
\ No newline at end of file
+}
+
+#[async_trait]
+pub trait SupplierCatalogRepository: Send + Sync {
+    async fn create_catalog_entry(&self, entry: &SupplierCatalogEntry) -> Result<SupplierCatalogEntry>;
+    async fn get_catalog_entry(&self, tenant_id: Uuid, entry_id: Uuid) -> Result<Option<SupplierCatalogEntry>>;
+    async fn update_catalog_entry(&self, entry: &SupplierCatalogEntry) -> Result<SupplierCatalogEntry>;
+    async fn delete_catalog_entry(&self, tenant_id: Uuid, entry_id: Uuid) -> Result<()>;
+    async fn get_catalog_entries_for_supplier(&self, tenant_id: Uuid, supplier_id: Uuid) -> Result<Vec<SupplierCatalogEntry>>;
+    async fn get_catalog_entries_for_product(&self, tenant_id: Uuid, product_id: Uuid) -> Result<Vec<SupplierCatalogEntry>>;
+}
+
+pub struct PostgresSupplierCatalogRepository {
+    db: DatabasePool,
+}
+
+impl PostgresSupplierCatalogRepository {
+    pub fn new(db: DatabasePool) -> Self {
+        Self { db }
+    }
+
+    fn get_pool(&self) -> &sqlx::PgPool {
+        &self.db.main_pool
+    }
+
+    fn map_row(row: &sqlx::postgres::PgRow) -> SupplierCatalogEntry {
+        let price_breaks: sqlx::types::Json<Vec<PriceBreak>> = row.get("price_breaks");
+        SupplierCatalogEntry {
+            id: row.get("id"),
+            tenant_id: row.get("tenant_id"),
+            supplier_id: row.get("supplier_id"),
+            product_id: row.get("product_id"),
+            supplier_sku: row.get("supplier_sku"),
+            currency: row.get("currency"),
+            price_breaks: price_breaks.0,
+            min_order_quantity: row.get("min_order_quantity"),
+            pack_size: row.get("pack_size"),
+            lead_time_days: row.get("lead_time_days"),
+            valid_from: row.get("valid_from"),
+            valid_to: row.get("valid_to"),
+            is_active: row.get("is_active"),
+            created_at: row.get("created_at"),
+            updated_at: row.get("updated_at"),
+            created_by: row.get("created_by"),
+            updated_by: row.get("updated_by"),
+        }
+    }
+}
+
+#[async_trait]
+impl SupplierCatalogRepository for PostgresSupplierCatalogRepository {
+    async fn create_catalog_entry(&self, entry: &SupplierCatalogEntry) -> Result<SupplierCatalogEntry> {
+        let query = r#"
+            INSERT INTO supplier_catalog_entries (
+                id, tenant_id, supplier_id, product_id, supplier_sku, currency, price_breaks,
+                min_order_quantity, pack_size, lead_time_days, valid_from, valid_to, is_active,
+                created_at, updated_at, created_by, updated_by
+            ) VALUES (
+                $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17
+            ) RETURNING *
+        "#;
+
+        let row = sqlx::query(query)
+            .bind(entry.id)
+            .bind(entry.tenant_id)
+            .bind(entry.supplier_id)
+            .bind(entry.product_id)
+            .bind(&entry.supplier_sku)
+            .bind(&entry.currency)
+            .bind(sqlx::types::Json(&entry.price_breaks))
+            .bind(entry.min_order_quantity)
+            .bind(entry.pack_size)
+            .bind(entry.lead_time_days)
+            .bind(entry.valid_from)
+            .bind(entry.valid_to)
+            .bind(entry.is_active)
+            .bind(entry.created_at)
+            .bind(entry.updated_at)
+            .bind(entry.created_by)
+            .bind(entry.updated_by)
+            .fetch_one(self.get_pool())
+            .await
+            .map_err(|e| Error::new(ErrorCode::DatabaseError, format!("Failed to create catalog entry: {}", e)))?;
+
+        Ok(Self::map_row(&row))
+    }
+
+    async fn get_catalog_entry(&self, tenant_id: Uuid, entry_id: Uuid) -> Result<Option<SupplierCatalogEntry>> {
+        let query = r#"
+            SELECT * FROM supplier_catalog_entries
+            WHERE id = $1 AND tenant_id = $2
+        "#;
+
+        let row = sqlx::query(query)
+            .bind(entry_id)
+            .bind(tenant_id)
+            .fetch_optional(self.get_pool())
+            .await
+            .map_err(|e| Error::new(ErrorCode::DatabaseError, format!("Failed to get catalog entry: {}", e)))?;
+
+        Ok(row.as_ref().map(Self::map_row))
+    }
+
+    async fn update_catalog_entry(&self, entry: &SupplierCatalogEntry) -> Result<SupplierCatalogEntry> {
+        let query = r#"
+            UPDATE supplier_catalog_entries SET
+                supplier_sku = $3, currency = $4, price_breaks = $5, min_order_quantity = $6,
+                pack_size = $7, lead_time_days = $8, valid_from = $9, valid_to = $10,
+                is_active = $11, updated_at = $12, updated_by = $13
+            WHERE id = $1 AND tenant_id = $2
+            RETURNING *
+        "#;
+
+        let row = sqlx::query(query)
+            .bind(entry.id)
+            .bind(entry.tenant_id)
+            .bind(&entry.supplier_sku)
+            .bind(&entry.currency)
+            .bind(sqlx::types::Json(&entry.price_breaks))
+            .bind(entry.min_order_quantity)
+            .bind(entry.pack_size)
+            .bind(entry.lead_time_days)
+            .bind(entry.valid_from)
+            .bind(entry.valid_to)
+            .bind(entry.is_active)
+            .bind(entry.updated_at)
+            .bind(entry.updated_by)
+            .fetch_optional(self.get_pool())
+            .await
+            .map_err(|e| Error::new(ErrorCode::DatabaseError, format!("Failed to update catalog entry: {}", e)))?
+            .ok_or_else(|| Error::new(ErrorCode::NotFound, "Catalog entry not found"))?;
+
+        Ok(Self::map_row(&row))
+    }
+
+    async fn delete_catalog_entry(&self, tenant_id: Uuid, entry_id: Uuid) -> Result<()> {
+        let query = r#"
+            DELETE FROM supplier_catalog_entries
+            WHERE id = $1 AND tenant_id = $2
+        "#;
+
+        let result = sqlx::query(query)
+            .bind(entry_id)
+            .bind(tenant_id)
+            .execute(self.get_pool())
+            .await
+            .map_err(|e| Error::new(ErrorCode::DatabaseError, format!("Failed to delete catalog entry: {}", e)))?;
+
+        if result.rows_affected() == 0 {
+            return Err(Error::new(ErrorCode::NotFound, "Catalog entry not found"));
+        }
+
+        Ok(())
+    }
+
+    async fn get_catalog_entries_for_supplier(&self, tenant_id: Uuid, supplier_id: Uuid) -> Result<Vec<SupplierCatalogEntry>> {
+        let query = r#"
+            SELECT * FROM supplier_catalog_entries
+            WHERE supplier_id = $1 AND tenant_id = $2
+            ORDER BY product_id, valid_from DESC
+        "#;
+
+        let rows = sqlx::query(query)
+            .bind(supplier_id)
+            .bind(tenant_id)
+            .fetch_all(self.get_pool())
+            .await
+            .map_err(|e| Error::new(ErrorCode::DatabaseError, format!("Failed to get supplier catalog: {}", e)))?;
+
+        Ok(rows.iter().map(Self::map_row).collect())
+    }
+
+    async fn get_catalog_entries_for_product(&self, tenant_id: Uuid, product_id: Uuid) -> Result<Vec<SupplierCatalogEntry>> {
+        let query = r#"
+            SELECT * FROM supplier_catalog_entries
+            WHERE product_id = $1 AND tenant_id = $2
+            ORDER BY supplier_id, valid_from DESC
+        "#;
+
+        let rows = sqlx::query(query)
+            .bind(product_id)
+            .bind(tenant_id)
+            .fetch_all(self.get_pool())
+            .await
+            .map_err(|e| Error::new(ErrorCode::DatabaseError, format!("Failed to get product catalog entries: {}", e)))?;
+
+        Ok(rows.iter().map(Self::map_row).collect())
+    }
+}
@@ -3,7 +3,11 @@
 //! This module provides business logic for supplier management,
 //! including validation, workflow orchestration, and business rules.
 
-use super::{model::*, repository::SupplierRepository};
+use super::{
+    catalog::*,
+    model::*,
+    repository::{SupplierCatalogRepository, SupplierRepository},
+};
 use crate::types::{PaginationOptions, PaginationResult, TenantContext};
 use async_trait::async_trait;
 use chrono::Utc;
@@ -820,4 +824,153 @@ pub struct SupplierPerformanceData {
     pub payment_compliance_rate: Option<f64>,
     pub overall_rating: Option<f64>,
     pub notes: Option<String>,
-}
\ No newline at end of file
+}
+
+/// Supplier price lists and quoting against them
+#[async_trait]
+pub trait SupplierCatalogService: Send + Sync {
+    async fn create_catalog_entry(&self, supplier_id: Uuid, request: CreateCatalogEntryRequest) -> Result<SupplierCatalogEntry>;
+    async fn update_catalog_entry(&self, supplier_id: Uuid, entry_id: Uuid, request: UpdateCatalogEntryRequest) -> Result<SupplierCatalogEntry>;
+    async fn delete_catalog_entry(&self, supplier_id: Uuid, entry_id: Uuid) -> Result<()>;
+    async fn get_supplier_catalog(&self, supplier_id: Uuid) -> Result<Vec<SupplierCatalogEntry>>;
+    async fn get_product_catalog_entries(&self, product_id: Uuid) -> Result<Vec<SupplierCatalogEntry>>;
+
+    /// Best valid catalog entry for buying `request.quantity` of a product
+    async fn quote_product(&self, product_id: Uuid, request: CatalogQuoteRequest) -> Result<Option<CatalogQuote>>;
+}
+
+pub struct DefaultSupplierCatalogService {
+    repository: Arc<dyn SupplierCatalogRepository>,
+    tenant_context: TenantContext,
+}
+
+impl DefaultSupplierCatalogService {
+    pub fn new(repository: Arc<dyn SupplierCatalogRepository>, tenant_context: TenantContext) -> Self {
+        Self {
+            repository,
+            tenant_context,
+        }
+    }
+
+    fn validate_entry(entry: &SupplierCatalogEntry) -> Result<()> {
+        if entry.currency.len() != 3 {
+            return Err(Error::new(ErrorCode::ValidationFailed, "Currency must be a 3-letter ISO code"));
+        }
+
+        if entry.min_order_quantity < 1 {
+            return Err(Error::new(ErrorCode::ValidationFailed, "Minimum order quantity must be at least 1"));
+        }
+
+        if entry.pack_size < 1 {
+            return Err(Error::new(ErrorCode::ValidationFailed, "Pack size must be at least 1"));
+        }
+
+        if entry.lead_time_days < 0 {
+            return Err(Error::new(ErrorCode::ValidationFailed, "Lead time cannot be negative"));
+        }
+
+        if let Some(valid_to) = entry.valid_to {
+            if valid_to <= entry.valid_from {
+                return Err(Error::new(ErrorCode::ValidationFailed, "Validity end must be after validity start"));
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn get_supplier_entry(&self, supplier_id: Uuid, entry_id: Uuid) -> Result<SupplierCatalogEntry> {
+        self.repository
+            .get_catalog_entry(self.tenant_context.tenant_id, entry_id)
+            .await?
+            .filter(|e| e.supplier_id == supplier_id)
+            .ok_or_else(|| Error::new(ErrorCode::NotFound, "Catalog entry not found"))
+    }
+}
+
+#[async_trait]
+impl SupplierCatalogService for DefaultSupplierCatalogService {
+    async fn create_catalog_entry(&self, supplier_id: Uuid, request: CreateCatalogEntryRequest) -> Result<SupplierCatalogEntry> {
+        let now = Utc::now();
+        let entry = SupplierCatalogEntry {
+            id: Uuid::new_v4(),
+            tenant_id: self.tenant_context.tenant_id,
+            supplier_id,
+            product_id: request.product_id,
+            supplier_sku: request.supplier_sku,
+            currency: request.currency.to_uppercase(),
+            price_breaks: normalize_price_breaks(request.price_breaks)?,
+            min_order_quantity: request.min_order_quantity.unwrap_or(1),
+            pack_size: request.pack_size.unwrap_or(1),
+            lead_time_days: request.lead_time_days,
+            valid_from: request.valid_from.unwrap_or(now),
+            valid_to: request.valid_to,
+            is_active: true,
+            created_at: now,
+            updated_at: now,
+            created_by: self.tenant_context.user_id,
+            updated_by: self.tenant_context.user_id,
+        };
+        Self::validate_entry(&entry)?;
+
+        self.repository.create_catalog_entry(&entry).await
+    }
+
+    async fn update_catalog_entry(&self, supplier_id: Uuid, entry_id: Uuid, request: UpdateCatalogEntryRequest) -> Result<SupplierCatalogEntry> {
+        let mut entry = self.get_supplier_entry(supplier_id, entry_id).await?;
+
+        if let Some(supplier_sku) = request.supplier_sku {
+            entry.supplier_sku = Some(supplier_sku);
+        }
+        if let Some(currency) = request.currency {
+            entry.currency = currency.to_uppercase();
+        }
+        if let Some(price_breaks) = request.price_breaks {
+            entry.price_breaks = normalize_price_breaks(price_breaks)?;
+        }
+        if let Some(min_order_quantity) = request.min_order_quantity {
+            entry.min_order_quantity = min_order_quantity;
+        }
+        if let Some(pack_size) = request.pack_size {
+            entry.pack_size = pack_size;
+        }
+        if let Some(lead_time_days) = request.lead_time_days {
+            entry.lead_time_days = lead_time_days;
+        }
+        if let Some(valid_from) = request.valid_from {
+            entry.valid_from = valid_from;
+        }
+        if let Some(valid_to) = request.valid_to {
+            entry.valid_to = Some(valid_to);
+        }
+        if let Some(is_active) = request.is_active {
+            entry.is_active = is_active;
+        }
+        Self::validate_entry(&entry)?;
+
+        entry.updated_at = Utc::now();
+        entry.updated_by = self.tenant_context.user_id;
+        self.repository.update_catalog_entry(&entry).await
+    }
+
+    async fn delete_catalog_entry(&self, supplier_id: Uuid, entry_id: Uuid) -> Result<()> {
+        self.get_supplier_entry(supplier_id, entry_id).await?;
+        self.repository.delete_catalog_entry(self.tenant_context.tenant_id, entry_id).await
+    }
+
+    async fn get_supplier_catalog(&self, supplier_id: Uuid) -> Result<Vec<SupplierCatalogEntry>> {
+        self.repository.get_catalog_entries_for_supplier(self.tenant_context.tenant_id, supplier_id).await
+    }
+
+    async fn get_product_catalog_entries(&self, product_id: Uuid) -> Result<Vec<SupplierCatalogEntry>> {
+        self.repository.get_catalog_entries_for_product(self.tenant_context.tenant_id, product_id).await
+    }
+
+    async fn quote_product(&self, product_id: Uuid, request: CatalogQuoteRequest) -> Result<Option<CatalogQuote>> {
+        if request.quantity <= 0 {
+            return Err(Error::new(ErrorCode::ValidationFailed, "Quantity must be positive"));
+        }
+
+        let entries = self.get_product_catalog_entries(product_id).await?;
+        Ok(select_best_entry(&entries, &request))
+    }
+}
@@ -19,6 +19,7 @@ pub struct AuditFields {
 
 /// Geographic coordinates
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct GeoCoordinates {
     pub latitude: f64,
     pub longitude: f64,
@@ -50,6 +51,31 @@ pub struct Address {
     pub audit: AuditFields,
 }
 
+impl Address {
+    /// The shared postal address value, for validation and formatting
+    pub fn postal_address(&self) -> erp_core::Address {
+        erp_core::Address {
+            country_code: self.country_code.clone(),
+            lines: std::iter::once(self.street_line_1.clone())
+                .chain(self.street_line_2.clone())
+                .collect(),
+            city: self.city.clone(),
+            region: self.state_province.clone(),
+            postal_code: Some(self.postal_code.clone()),
+            coordinates: self.coordinates.as_ref().map(GeoCoordinates::point),
+        }
+    }
+}
+
+impl GeoCoordinates {
+    pub fn point(&self) -> erp_core::GeoPoint {
+        erp_core::GeoPoint {
+            latitude: self.latitude,
+            longitude: self.longitude,
+        }
+    }
+}
+
 /// Contact information
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
 pub struct ContactInfo {
@@ -88,6 +114,7 @@ pub struct ContactInfo {
 
 /// Communication preferences
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct CommunicationPreferences {
     pub email_notifications: bool,
     pub sms_notifications: bool,
@@ -106,10 +133,23 @@ pub struct FinancialInfo {
     pub payment_terms: Option<PaymentTerms>,
     pub tax_exempt: bool,
     pub tax_numbers: HashMap<String, String>, // Tax type -> Tax number
+    /// Stored encrypted, see `customer::field_encryption`
+    #[serde(default)]
+    pub bank_accounts: Vec<BankAccount>,
+}
+
+/// Bank account a customer pays from or is refunded to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct BankAccount {
+    pub iban: String,
+    pub bic: Option<String>,
+    pub account_holder: Option<String>,
 }
 
 /// Payment terms
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct PaymentTerms {
     pub payment_method: PaymentMethod,
     pub net_days: Option<i32>,
@@ -120,6 +160,7 @@ pub struct PaymentTerms {
 
 /// Data synchronization information
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct SyncInfo {
     pub last_sync: Option<DateTime<Utc>>,
     pub sync_source: Option<String>,
@@ -131,6 +172,7 @@ pub struct SyncInfo {
 // Enumerations
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[sqlx(type_name = "address_type", rename_all = "snake_case")]
 pub enum AddressType {
     Billing,
@@ -145,6 +187,7 @@ pub enum AddressType {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[sqlx(type_name = "contact_type", rename_all = "snake_case")]
 pub enum ContactType {
     Primary,
@@ -161,6 +204,7 @@ pub enum ContactType {
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, sqlx::Type)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[sqlx(type_name = "payment_method", rename_all = "snake_case")]
 pub enum PaymentMethod {
     Cash,
@@ -187,6 +231,7 @@ pub enum DataSource {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::Type)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[sqlx(type_name = "sync_status", rename_all = "snake_case")]
 pub enum SyncStatus {
     NotSynced,
@@ -200,6 +245,7 @@ pub enum SyncStatus {
 
 /// Generic status for entities
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::Type)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[sqlx(type_name = "entity_status", rename_all = "snake_case")]
 pub enum EntityStatus {
     Active,
@@ -222,7 +268,8 @@ pub enum RiskRating {
 }
 
 /// Business size classifications
-#[derive(Debug, Clone, Serialize, Deserialize, sqlx::Type)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[sqlx(type_name = "business_size", rename_all = "snake_case")]
 pub enum BusinessSize {
     Micro,     // < 10 employees
@@ -234,6 +281,7 @@ pub enum BusinessSize {
 
 /// Industry classification systems
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::Type, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[sqlx(type_name = "industry_classification", rename_all = "snake_case")]
 pub enum IndustryClassification {
     Technology,
@@ -288,6 +336,7 @@ impl Default for FinancialInfo {
             payment_terms: Some(PaymentTerms::default()),
             tax_exempt: false,
             tax_numbers: HashMap::new(),
+            bank_accounts: Vec::new(),
         }
     }
 }
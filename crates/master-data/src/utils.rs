@@ -242,6 +242,7 @@ pub fn convert_to_location_type(location_type: Option<String>) -> Option<crate::
         Some("store") => Some(LocationType::Store),
         Some("distribution_center") => Some(LocationType::DistributionCenter),
         Some("supplier") => Some(LocationType::Supplier),
+        Some("quarantine") => Some(LocationType::Quarantine),
         _ => None,
     }
 }
@@ -321,6 +322,7 @@ pub fn string_to_location_type(s: String) -> crate::inventory::model::LocationTy
         "customer" => LocationType::Customer,
         "transit" => LocationType::Transit,
         "virtual" => LocationType::Virtual,
+        "quarantine" => LocationType::Quarantine,
         _ => LocationType::Warehouse, // Default
     }
 }
@@ -0,0 +1,351 @@
+//! Golden tests of the inventory figures finance reports: stock value under
+//! FIFO and weighted average, cost of goods sold, turnover, days inventory
+//! outstanding, stock aging and the replenishment quantities (safety stock,
+//! reorder point, EOQ).
+//!
+//! Each scenario is a small month of movements in
+//! `tests/golden/inventory/<scenario>.toml`, checked against the literal
+//! values of `<scenario>.expected.toml`. Amounts and ratios are compared
+//! exactly, the floating-point replenishment figures within `EPSILON`. The
+//! expected files state the formula behind every figure in a comment.
+//!
+//! ## Changing a formula
+//!
+//! A change of the results fails these tests. When the change is intended,
+//! regenerate the expected files and commit them with it, so the changed
+//! figures show up in review:
+//!
+//! ```bash
+//! ERP_UPDATE_GOLDEN=1 cargo test -p erp-master-data --test inventory_golden
+//! git diff crates/master-data/tests/golden
+//! ```
+
+use chrono::{Duration, NaiveDate, TimeZone, Utc};
+use erp_master_data::inventory::costing::CostedMovement;
+use erp_master_data::inventory::explanation::{compute_recommendation, DemandStatistics, RecommendationInputs};
+use erp_master_data::inventory::kpi::{daily_demand, period_report, CostFlow, PeriodReport};
+use erp_master_data::inventory::AgingCategory;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use std::fmt::Write;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+/// Tolerance of the floating-point figures
+const EPSILON: f64 = 1e-9;
+
+/// Set to rewrite the expected files from the current code
+const UPDATE_ENV: &str = "ERP_UPDATE_GOLDEN";
+
+#[derive(Debug, Deserialize)]
+struct Fixture {
+    period: Period,
+    replenishment: Replenishment,
+    movements: Vec<FixtureMovement>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Period {
+    from: NaiveDate,
+    to: NaiveDate,
+    #[serde(default)]
+    reserved: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct Replenishment {
+    lead_time_days: f64,
+    service_level: f64,
+    ordering_cost: f64,
+    holding_cost_rate: f64,
+}
+
+#[derive(Debug, Deserialize)]
+struct FixtureMovement {
+    day: NaiveDate,
+    #[serde(rename = "type")]
+    movement_type: String,
+    quantity: i32,
+    /// A decimal string, so it is read exactly
+    unit_cost: Option<String>,
+}
+
+fn golden_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden/inventory")
+}
+
+fn load(scenario: &str) -> Fixture {
+    let path = golden_dir().join(format!("{}.toml", scenario));
+    let text = std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("read {}: {}", path.display(), e));
+    toml::from_str(&text).unwrap_or_else(|e| panic!("parse {}: {}", path.display(), e))
+}
+
+/// The fixture's movements; those of one day occur a second apart, in file order
+fn movements(fixture: &Fixture) -> Vec<CostedMovement> {
+    fixture
+        .movements
+        .iter()
+        .enumerate()
+        .map(|(index, m)| CostedMovement {
+            id: Uuid::new_v4(),
+            product_id: Uuid::nil(),
+            location_id: Uuid::nil(),
+            movement_type: m.movement_type.clone(),
+            quantity: m.quantity,
+            unit_cost: m.unit_cost.as_deref().map(|cost| {
+                cost.parse::<Decimal>().unwrap_or_else(|e| panic!("unit cost {:?}: {}", cost, e))
+            }),
+            cost_source: None,
+            cost_needs_review: false,
+            reversal_of: None,
+            restatement_of: None,
+            reference_document: None,
+            reference_number: None,
+            reason: None,
+            occurred_at: Utc.from_utc_datetime(&m.day.and_hms_opt(12, 0, 0).unwrap()) + Duration::seconds(index as i64),
+        })
+        .collect()
+}
+
+fn category_key(category: AgingCategory) -> &'static str {
+    match category {
+        AgingCategory::Current => "current",
+        AgingCategory::Slow => "slow",
+        AgingCategory::Dead => "dead",
+        AgingCategory::VeryDead => "very_dead",
+    }
+}
+
+fn write_report(out: &mut String, report: &PeriodReport) {
+    let v = &report.valuation;
+    let k = &report.kpis;
+    let flow = v.cost_flow.as_str();
+
+    writeln!(out, "[{}.valuation]", flow).unwrap();
+    writeln!(out, "# units on hand and their value before {}", report.from).unwrap();
+    writeln!(out, "opening_quantity = {}", v.opening_quantity).unwrap();
+    writeln!(out, "opening_value = \"{:.2}\"", v.opening_value).unwrap();
+    writeln!(out, "received_quantity = {}", v.received_quantity).unwrap();
+    writeln!(out, "received_value = \"{:.2}\"", v.received_value).unwrap();
+    writeln!(out, "# shipments and consumption").unwrap();
+    writeln!(out, "cost_of_goods_sold = \"{:.2}\"", v.cost_of_goods_sold).unwrap();
+    writeln!(out, "# every other outbound movement").unwrap();
+    writeln!(out, "write_downs = \"{:.2}\"", v.write_downs).unwrap();
+    writeln!(out, "closing_quantity = {}", v.closing_quantity).unwrap();
+    writeln!(
+        out,
+        "# opening + received - cost of goods sold - write-downs = {:.2} + {:.2} - {:.2} - {:.2}",
+        v.opening_value, v.received_value, v.cost_of_goods_sold, v.write_downs
+    )
+    .unwrap();
+    writeln!(out, "closing_value = \"{:.2}\"", v.closing_value).unwrap();
+
+    writeln!(out, "\n[{}.kpis]", flow).unwrap();
+    writeln!(out, "# {} to {}, both included", report.from, report.to).unwrap();
+    writeln!(out, "days = {}", k.days).unwrap();
+    writeln!(
+        out,
+        "# (opening value + closing value) / 2 = ({:.2} + {:.2}) / 2",
+        v.opening_value, v.closing_value
+    )
+    .unwrap();
+    writeln!(out, "average_inventory_value = \"{:.4}\"", k.average_inventory_value).unwrap();
+    match k.turnover {
+        Some(turnover) => {
+            writeln!(
+                out,
+                "# cost of goods sold / average inventory value = {:.2} / {:.4}",
+                k.cost_of_goods_sold, k.average_inventory_value
+            )
+            .unwrap();
+            writeln!(out, "turnover = \"{:.4}\"", turnover).unwrap();
+        }
+        None => writeln!(out, "# turnover: undefined without inventory value").unwrap(),
+    }
+    match k.days_inventory_outstanding {
+        Some(dio) => {
+            writeln!(
+                out,
+                "# average inventory value / cost of goods sold x days = {:.4} / {:.2} x {}",
+                k.average_inventory_value, k.cost_of_goods_sold, k.days
+            )
+            .unwrap();
+            writeln!(out, "days_inventory_outstanding = \"{:.4}\"", dio).unwrap();
+        }
+        None => writeln!(out, "# days_inventory_outstanding: undefined without cost of goods sold").unwrap(),
+    }
+
+    writeln!(out, "\n[{}.aging]", flow).unwrap();
+    writeln!(
+        out,
+        "# closing stock by the age of its receipt on {} (oldest issued first), at receipt cost;\n\
+         # current 0-30 days, slow 31-90, dead 91-180, very_dead over 180",
+        report.to
+    )
+    .unwrap();
+    for bucket in &report.aging {
+        writeln!(
+            out,
+            "{} = {{ quantity = {}, value = \"{:.2}\" }}",
+            category_key(bucket.category),
+            bucket.quantity,
+            bucket.value
+        )
+        .unwrap();
+    }
+    out.push('\n');
+}
+
+/// The expected file of a scenario, from the current code
+fn render(scenario: &str, fixture: &Fixture) -> String {
+    let movements = movements(fixture);
+    let (from, to) = (fixture.period.from, fixture.period.to);
+    let mut out = String::new();
+    writeln!(out, "# Expected figures of {}.toml, generated by tests/inventory_golden.rs.", scenario).unwrap();
+    writeln!(out, "# Regenerate with {}=1 instead of editing by hand.\n", UPDATE_ENV).unwrap();
+
+    let mut available = None;
+    for cost_flow in [CostFlow::Fifo, CostFlow::WeightedAverage] {
+        let report = period_report(&movements, cost_flow, from, to, fixture.period.reserved)
+            .unwrap_or_else(|e| panic!("{} under {:?}: {}", scenario, cost_flow, e));
+        available = Some((report.valuation.closing_quantity, report.reserved, report.available));
+        write_report(&mut out, &report);
+    }
+
+    let (closing, reserved, available) = available.expect("a cost flow");
+    writeln!(out, "[stock]").unwrap();
+    writeln!(out, "reserved = {}", reserved).unwrap();
+    writeln!(out, "# closing quantity - reserved, not below zero = {} - {}", closing, reserved).unwrap();
+    writeln!(out, "available = {}\n", available).unwrap();
+
+    let demand = daily_demand(&movements, from, to);
+    let stats = DemandStatistics::from_daily_demand(demand.len() as i32, &demand);
+    writeln!(out, "[replenishment.demand]").unwrap();
+    writeln!(out, "# units shipped or consumed on each day of the period, days without sales as zero").unwrap();
+    writeln!(out, "observations = {}", stats.observations).unwrap();
+    writeln!(out, "# mean = sum / n = {} / {}", demand.iter().sum::<f64>(), stats.observations).unwrap();
+    writeln!(out, "mean_daily_demand = {:?}", stats.mean_daily_demand).unwrap();
+    writeln!(out, "# sigma = sqrt(sample variance) = sqrt({:?})", stats.variance).unwrap();
+    writeln!(out, "std_dev = {:?}", stats.std_dev).unwrap();
+    writeln!(out, "# least-squares slope over the days").unwrap();
+    writeln!(out, "trend_per_day = {:?}\n", stats.trend_per_day).unwrap();
+
+    let r = &fixture.replenishment;
+    let (calculation, _) = compute_recommendation(&RecommendationInputs {
+        mean_daily_demand: stats.mean_daily_demand,
+        demand_std_dev: stats.std_dev,
+        lead_time_days: r.lead_time_days,
+        service_level: r.service_level,
+        ordering_cost: r.ordering_cost,
+        holding_cost_rate: r.holding_cost_rate,
+        constraints: Default::default(),
+    });
+    writeln!(out, "[replenishment.calculation]").unwrap();
+    for step in &calculation.steps {
+        writeln!(out, "# {} = {}", step.formula, step.substituted).unwrap();
+        writeln!(out, "{} = {:?}", step.quantity, step.value).unwrap();
+    }
+    writeln!(out, "# the EOQ, without ordering constraints").unwrap();
+    writeln!(out, "order_quantity = {:?}", calculation.order_quantity).unwrap();
+    out
+}
+
+/// Compare the parsed files, naming the first figure that differs
+fn assert_same(path: &str, expected: &toml::Value, actual: &toml::Value) {
+    match (expected, actual) {
+        (toml::Value::Table(expected), toml::Value::Table(actual)) => {
+            for key in expected.keys().chain(actual.keys()) {
+                let path = format!("{}.{}", path, key);
+                match (expected.get(key), actual.get(key)) {
+                    (Some(expected), Some(actual)) => assert_same(&path, expected, actual),
+                    (expected, actual) => panic!("{}: expected {:?}, got {:?}", path, expected, actual),
+                }
+            }
+        }
+        (toml::Value::Float(expected), toml::Value::Float(actual)) => {
+            assert!(
+                (expected - actual).abs() <= EPSILON,
+                "{}: expected {}, got {}",
+                path,
+                expected,
+                actual
+            );
+        }
+        (expected, actual) => assert_eq!(expected, actual, "{}", path),
+    }
+}
+
+fn check(scenario: &str) -> Fixture {
+    let fixture = load(scenario);
+    let rendered = render(scenario, &fixture);
+    let path = golden_dir().join(format!("{}.expected.toml", scenario));
+
+    if std::env::var_os(UPDATE_ENV).is_some() {
+        std::fs::write(&path, &rendered).unwrap_or_else(|e| panic!("write {}: {}", path.display(), e));
+        return fixture;
+    }
+
+    let expected = std::fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("read {} (generate it with {}=1): {}", path.display(), UPDATE_ENV, e));
+    let expected: toml::Value = toml::from_str(&expected).unwrap_or_else(|e| panic!("parse {}: {}", path.display(), e));
+    let actual: toml::Value = toml::from_str(&rendered).expect("the rendered figures are valid TOML");
+    assert_same(scenario, &expected, &actual);
+    fixture
+}
+
+fn report(fixture: &Fixture, cost_flow: CostFlow) -> PeriodReport {
+    period_report(&movements(fixture), cost_flow, fixture.period.from, fixture.period.to, fixture.period.reserved)
+        .unwrap()
+}
+
+#[test]
+fn test_baseline_month() {
+    check("baseline_month");
+}
+
+#[test]
+fn test_fifo_and_weighted_average_diverge_on_rising_costs() {
+    let fixture = check("rising_costs");
+
+    let fifo = report(&fixture, CostFlow::Fifo).valuation;
+    let average = report(&fixture, CostFlow::WeightedAverage).valuation;
+    assert_eq!(fifo.closing_quantity, average.closing_quantity);
+    // FIFO issues the cheap old units, leaving the stock at the latest costs
+    assert!(fifo.closing_value > average.closing_value);
+    assert!(fifo.cost_of_goods_sold < average.cost_of_goods_sold);
+}
+
+#[test]
+fn test_month_without_sales() {
+    let fixture = check("no_sales");
+
+    let kpis = report(&fixture, CostFlow::Fifo).kpis;
+    assert_eq!(kpis.turnover, Some(Decimal::ZERO));
+    assert_eq!(kpis.days_inventory_outstanding, None);
+}
+
+#[test]
+fn test_sales_of_uncosted_stock_have_zero_cost() {
+    let fixture = check("zero_cogs");
+
+    let kpis = report(&fixture, CostFlow::WeightedAverage).kpis;
+    assert_eq!(kpis.cost_of_goods_sold, Decimal::ZERO);
+    assert_eq!(kpis.turnover, None);
+    assert_eq!(kpis.days_inventory_outstanding, None);
+}
+
+#[test]
+fn test_negative_adjustments_are_written_down() {
+    let fixture = check("negative_adjustments");
+
+    for cost_flow in [CostFlow::Fifo, CostFlow::WeightedAverage] {
+        let v = report(&fixture, cost_flow).valuation;
+        assert!(v.write_downs > Decimal::ZERO, "{:?}", cost_flow);
+        assert_eq!(
+            v.opening_value + v.received_value - v.cost_of_goods_sold - v.write_downs,
+            v.closing_value,
+            "{:?}",
+            cost_flow
+        );
+    }
+}
@@ -0,0 +1,27 @@
+-- Tenant schema migration and provisioning tracking
+-- Used by the admin tenant health API to spot tenants whose schema lags behind
+-- or whose provisioning never finished.
+
+CREATE TABLE IF NOT EXISTS public.tenant_schema_migrations (
+    tenant_id UUID NOT NULL REFERENCES public.tenants(id) ON DELETE CASCADE,
+    version BIGINT NOT NULL,
+    description TEXT NOT NULL,
+    success BOOLEAN NOT NULL,
+    applied_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (tenant_id, version)
+);
+
+CREATE TABLE IF NOT EXISTS public.tenant_provisioning_steps (
+    tenant_id UUID NOT NULL REFERENCES public.tenants(id) ON DELETE CASCADE,
+    step VARCHAR(100) NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'pending'
+        CHECK (status IN ('pending', 'running', 'complete', 'failed')),
+    error TEXT,
+    started_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (tenant_id, step)
+);
+
+CREATE INDEX IF NOT EXISTS idx_tenant_provisioning_steps_status
+    ON public.tenant_provisioning_steps(status)
+    WHERE status <> 'complete';
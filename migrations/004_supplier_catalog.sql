@@ -0,0 +1,31 @@
+-- Supplier product catalog / price lists
+-- One row per supplier offer for a product. Price breaks are stored as a JSON
+-- array of {"min_quantity", "unit_price"} objects, unit prices in cents.
+
+CREATE TABLE IF NOT EXISTS public.supplier_catalog_entries (
+    id UUID PRIMARY KEY,
+    tenant_id UUID NOT NULL,
+    supplier_id UUID NOT NULL,
+    product_id UUID NOT NULL,
+    supplier_sku VARCHAR(100),
+    currency CHAR(3) NOT NULL,
+    price_breaks JSONB NOT NULL DEFAULT '[]'::jsonb,
+    min_order_quantity INTEGER NOT NULL DEFAULT 1 CHECK (min_order_quantity >= 1),
+    pack_size INTEGER NOT NULL DEFAULT 1 CHECK (pack_size >= 1),
+    lead_time_days INTEGER NOT NULL DEFAULT 0 CHECK (lead_time_days >= 0),
+    valid_from TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    valid_to TIMESTAMPTZ,
+    is_active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    created_by UUID NOT NULL,
+    updated_by UUID NOT NULL,
+    CHECK (valid_to IS NULL OR valid_to > valid_from)
+);
+
+CREATE INDEX IF NOT EXISTS idx_supplier_catalog_entries_product
+    ON public.supplier_catalog_entries(tenant_id, product_id)
+    WHERE is_active;
+
+CREATE INDEX IF NOT EXISTS idx_supplier_catalog_entries_supplier
+    ON public.supplier_catalog_entries(tenant_id, supplier_id);
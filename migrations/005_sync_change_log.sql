@@ -0,0 +1,76 @@
+-- Change log for differential sync of offline clients
+-- Every insert, update and (soft) delete on synced tables appends a row with a
+-- monotonically increasing change_seq. Clients pull changes after a cursor.
+-- Old rows are compacted away; change_log_horizon remembers, per tenant and
+-- entity type, the highest sequence removed so stale cursors can be detected.
+
+CREATE TABLE IF NOT EXISTS public.change_log (
+    change_seq BIGSERIAL PRIMARY KEY,
+    tenant_id UUID NOT NULL,
+    entity_type VARCHAR(50) NOT NULL,
+    entity_id UUID NOT NULL,
+    operation VARCHAR(10) NOT NULL CHECK (operation IN ('created', 'updated', 'deleted')),
+    changed_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_change_log_tenant_entity_seq
+    ON public.change_log(tenant_id, entity_type, change_seq);
+
+CREATE INDEX IF NOT EXISTS idx_change_log_changed_at
+    ON public.change_log(changed_at);
+
+CREATE TABLE IF NOT EXISTS public.change_log_horizon (
+    tenant_id UUID NOT NULL,
+    entity_type VARCHAR(50) NOT NULL,
+    compacted_through BIGINT NOT NULL DEFAULT 0,
+    compacted_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (tenant_id, entity_type)
+);
+
+CREATE OR REPLACE FUNCTION public.record_entity_change() RETURNS TRIGGER AS $$
+DECLARE
+    op VARCHAR(10);
+    rec RECORD;
+BEGIN
+    IF TG_OP = 'INSERT' THEN
+        op := 'created';
+        rec := NEW;
+    ELSIF TG_OP = 'DELETE' THEN
+        op := 'deleted';
+        rec := OLD;
+    ELSE
+        rec := NEW;
+        -- Soft deletes flip is_deleted; they must reach clients as tombstones
+        IF (to_jsonb(NEW) ->> 'is_deleted')::boolean IS TRUE THEN
+            IF (to_jsonb(OLD) ->> 'is_deleted')::boolean IS TRUE THEN
+                RETURN NULL;
+            END IF;
+            op := 'deleted';
+        ELSE
+            op := 'updated';
+        END IF;
+    END IF;
+
+    INSERT INTO public.change_log (tenant_id, entity_type, entity_id, operation)
+    VALUES (rec.tenant_id, TG_ARGV[0], rec.id, op);
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DO $$
+BEGIN
+    IF to_regclass('public.customers') IS NOT NULL THEN
+        DROP TRIGGER IF EXISTS trg_customers_change_log ON public.customers;
+        CREATE TRIGGER trg_customers_change_log
+            AFTER INSERT OR UPDATE OR DELETE ON public.customers
+            FOR EACH ROW EXECUTE FUNCTION public.record_entity_change('customer');
+    END IF;
+
+    IF to_regclass('public.products') IS NOT NULL THEN
+        DROP TRIGGER IF EXISTS trg_products_change_log ON public.products;
+        CREATE TRIGGER trg_products_change_log
+            AFTER INSERT OR UPDATE OR DELETE ON public.products
+            FOR EACH ROW EXECUTE FUNCTION public.record_entity_change('product');
+    END IF;
+END;
+$$;
@@ -0,0 +1,50 @@
+-- Serial number tracking for serialized products
+-- One row per physical unit; serial numbers are unique per tenant. Every
+-- status or location change is appended to serial_unit_events.
+
+ALTER TABLE public.products
+    ADD COLUMN IF NOT EXISTS is_serialized BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Persist the serials an inventory movement touched (previously write-only)
+ALTER TABLE public.inventory_transactions
+    ADD COLUMN IF NOT EXISTS serial_numbers TEXT[];
+
+CREATE TABLE IF NOT EXISTS public.serial_units (
+    id UUID PRIMARY KEY,
+    tenant_id UUID NOT NULL,
+    product_id UUID NOT NULL,
+    serial_number VARCHAR(100) NOT NULL,
+    location_id UUID,
+    bin_location VARCHAR(50),
+    status VARCHAR(20) NOT NULL
+        CHECK (status IN ('in_stock', 'reserved', 'shipped', 'returned', 'scrapped')),
+    receipt_movement_id UUID NOT NULL,
+    received_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (status = 'shipped' OR location_id IS NOT NULL)
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_serial_units_tenant_serial
+    ON public.serial_units(tenant_id, serial_number);
+
+CREATE INDEX IF NOT EXISTS idx_serial_units_product_status
+    ON public.serial_units(tenant_id, product_id, status, received_at);
+
+CREATE TABLE IF NOT EXISTS public.serial_unit_events (
+    id UUID PRIMARY KEY,
+    tenant_id UUID NOT NULL,
+    serial_unit_id UUID NOT NULL REFERENCES public.serial_units(id) ON DELETE CASCADE,
+    serial_number VARCHAR(100) NOT NULL,
+    product_id UUID NOT NULL,
+    from_status VARCHAR(20),
+    to_status VARCHAR(20) NOT NULL,
+    from_location_id UUID,
+    to_location_id UUID,
+    movement_id UUID,
+    reference VARCHAR(255),
+    performed_by UUID NOT NULL,
+    occurred_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_serial_unit_events_unit
+    ON public.serial_unit_events(serial_unit_id, occurred_at);
@@ -0,0 +1,42 @@
+-- Customer communication log
+-- Emails, calls, meetings and notes per customer with optional follow-ups.
+-- Entries written by the email service on a customer's behalf are flagged
+-- is_system_generated.
+
+CREATE TABLE IF NOT EXISTS public.customer_communications (
+    id UUID PRIMARY KEY,
+    tenant_id UUID NOT NULL,
+    customer_id UUID NOT NULL,
+    communication_type VARCHAR(20) NOT NULL
+        CHECK (communication_type IN ('email', 'call', 'meeting', 'note')),
+    direction VARCHAR(20) NOT NULL
+        CHECK (direction IN ('inbound', 'outbound', 'internal')),
+    subject VARCHAR(255) NOT NULL,
+    body TEXT,
+    occurred_at TIMESTAMPTZ NOT NULL,
+    logged_by UUID,
+    is_system_generated BOOLEAN NOT NULL DEFAULT FALSE,
+    follow_up_due TIMESTAMPTZ,
+    follow_up_done BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (is_system_generated OR logged_by IS NOT NULL)
+);
+
+CREATE INDEX IF NOT EXISTS idx_customer_communications_customer
+    ON public.customer_communications(tenant_id, customer_id, occurred_at DESC);
+
+-- "My follow-ups" and the daily reminder scan only touch open follow-ups
+CREATE INDEX IF NOT EXISTS idx_customer_communications_open_follow_ups
+    ON public.customer_communications(tenant_id, logged_by, follow_up_due)
+    WHERE follow_up_due IS NOT NULL AND follow_up_done = FALSE;
+
+-- Per-user notification opt-outs; a missing row means the notification is on
+CREATE TABLE IF NOT EXISTS public.notification_preferences (
+    tenant_id UUID NOT NULL,
+    user_id UUID NOT NULL,
+    notification_type VARCHAR(50) NOT NULL,
+    email_enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (tenant_id, user_id, notification_type)
+);
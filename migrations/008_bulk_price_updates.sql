@@ -0,0 +1,19 @@
+-- Applied bulk price updates
+-- The diff keeps every product's price before and after the update, so a
+-- rollback can restore the exact previous prices.
+
+CREATE TABLE IF NOT EXISTS public.bulk_price_updates (
+    id UUID PRIMARY KEY,
+    tenant_id UUID NOT NULL,
+    adjustment JSONB NOT NULL,
+    diff JSONB NOT NULL,
+    guard_override BOOLEAN NOT NULL DEFAULT FALSE,
+    performed_by UUID NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    rolled_back_at TIMESTAMPTZ,
+    rolled_back_by UUID,
+    CHECK ((rolled_back_at IS NULL) = (rolled_back_by IS NULL))
+);
+
+CREATE INDEX IF NOT EXISTS idx_bulk_price_updates_tenant_created
+    ON public.bulk_price_updates(tenant_id, created_at DESC);
@@ -0,0 +1,14 @@
+-- Inventory optimization reports
+-- The whole report is stored, including each recommendation's explanation,
+-- so a past recommendation can still be explained after parameters change.
+
+CREATE TABLE IF NOT EXISTS public.inventory_optimization_reports (
+    id UUID PRIMARY KEY,
+    tenant_id UUID NOT NULL,
+    location_id UUID NOT NULL,
+    report JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_inventory_optimization_reports_location
+    ON public.inventory_optimization_reports(tenant_id, location_id, created_at DESC);
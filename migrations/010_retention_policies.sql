@@ -0,0 +1,43 @@
+-- Data retention policies and their enforcement log
+-- A policy without a tenant is the global default for its category; a tenant
+-- policy overrides it. Categories without any policy use the default they
+-- were registered with.
+
+CREATE TABLE IF NOT EXISTS public.retention_policies (
+    id BIGSERIAL PRIMARY KEY,
+    tenant_id UUID,
+    category VARCHAR(100) NOT NULL,
+    retention_days INTEGER NOT NULL CHECK (retention_days >= 1),
+    action VARCHAR(20) NOT NULL CHECK (action IN ('delete', 'anonymize', 'archive')),
+    updated_by UUID,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_retention_policies_tenant_category
+    ON public.retention_policies(tenant_id, category) WHERE tenant_id IS NOT NULL;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_retention_policies_global_category
+    ON public.retention_policies(category) WHERE tenant_id IS NULL;
+
+CREATE TABLE IF NOT EXISTS public.retention_enforcement_log (
+    id UUID PRIMARY KEY,
+    tenant_id UUID NOT NULL,
+    category VARCHAR(100) NOT NULL,
+    action VARCHAR(20) NOT NULL,
+    retention_days INTEGER NOT NULL,
+    policy_source VARCHAR(20) NOT NULL CHECK (policy_source IN ('tenant', 'global', 'builtin')),
+    cutoff TIMESTAMPTZ NOT NULL,
+    rows_affected BIGINT NOT NULL DEFAULT 0,
+    batches INTEGER NOT NULL DEFAULT 0,
+    completed BOOLEAN NOT NULL,
+    error TEXT,
+    started_at TIMESTAMPTZ NOT NULL,
+    finished_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_retention_enforcement_log_tenant_started
+    ON public.retention_enforcement_log(tenant_id, started_at DESC);
+CREATE INDEX IF NOT EXISTS idx_retention_enforcement_log_started
+    ON public.retention_enforcement_log(started_at DESC);
+
+-- Anonymized communication entries keep their row for counts and history
+ALTER TABLE public.customer_communications ADD COLUMN IF NOT EXISTS anonymized_at TIMESTAMPTZ;
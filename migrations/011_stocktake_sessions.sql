@@ -0,0 +1,42 @@
+-- Physical stocktake sessions
+-- Scanner files are stored line by line so corrected files can be uploaded
+-- again: a line's checksum identifies it within its file. Computed variances
+-- are kept on the session until it is submitted as cycle counts.
+
+CREATE TABLE IF NOT EXISTS public.stocktake_sessions (
+    id UUID PRIMARY KEY,
+    tenant_id UUID NOT NULL,
+    name VARCHAR(255) NOT NULL,
+    snapshot_at TIMESTAMPTZ NOT NULL,
+    status VARCHAR(20) NOT NULL CHECK (status IN ('open', 'submitted')),
+    lines JSONB NOT NULL DEFAULT '[]',
+    unmatched_barcodes JSONB NOT NULL DEFAULT '[]',
+    unknown_locations TEXT[] NOT NULL DEFAULT '{}',
+    cycle_count_ids JSONB NOT NULL DEFAULT '{}',
+    created_by UUID NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    submitted_by UUID,
+    submitted_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_stocktake_sessions_tenant
+    ON public.stocktake_sessions(tenant_id, created_at DESC);
+
+CREATE TABLE IF NOT EXISTS public.stocktake_scan_lines (
+    id UUID PRIMARY KEY,
+    tenant_id UUID NOT NULL,
+    session_id UUID NOT NULL REFERENCES public.stocktake_sessions(id) ON DELETE CASCADE,
+    file_name VARCHAR(255) NOT NULL,
+    line_number INTEGER NOT NULL,
+    location_code VARCHAR(50) NOT NULL,
+    barcode VARCHAR(100) NOT NULL,
+    counted_quantity INTEGER NOT NULL CHECK (counted_quantity >= 0),
+    counter_id VARCHAR(100) NOT NULL,
+    scanned_at TIMESTAMPTZ NOT NULL,
+    checksum CHAR(64) NOT NULL,
+    UNIQUE (session_id, file_name, checksum)
+);
+
+CREATE INDEX IF NOT EXISTS idx_stocktake_scan_lines_session
+    ON public.stocktake_scan_lines(tenant_id, session_id);
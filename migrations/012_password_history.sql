@@ -0,0 +1,24 @@
+-- Password history and per-tenant password policy
+-- History rows hold previous password hashes so a password change or reset
+-- can refuse recently used passwords. A tenant without a policy row uses the
+-- [password_policy] defaults from configuration.
+
+CREATE TABLE IF NOT EXISTS public.password_history (
+    id BIGSERIAL PRIMARY KEY,
+    tenant_id UUID NOT NULL,
+    user_id UUID NOT NULL,
+    password_hash TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_password_history_tenant_user_created
+    ON public.password_history(tenant_id, user_id, created_at DESC);
+
+CREATE TABLE IF NOT EXISTS public.tenant_password_policies (
+    tenant_id UUID PRIMARY KEY,
+    min_length INTEGER NOT NULL CHECK (min_length >= 8),
+    require_complexity BOOLEAN NOT NULL DEFAULT TRUE,
+    history_count INTEGER NOT NULL DEFAULT 5 CHECK (history_count >= 0),
+    updated_by UUID,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,60 @@
+-- Customer return orders (RMAs)
+-- Returned goods are booked into a quarantine location on receipt and moved
+-- out of it by the per-line inspection disposition. Quarantine locations are
+-- never counted as sellable stock.
+
+DO $$
+BEGIN
+    IF EXISTS (SELECT 1 FROM pg_type WHERE typname = 'location_type') THEN
+        ALTER TYPE location_type ADD VALUE IF NOT EXISTS 'quarantine';
+    END IF;
+END
+$$;
+
+CREATE TABLE IF NOT EXISTS public.return_orders (
+    id UUID PRIMARY KEY,
+    tenant_id UUID NOT NULL,
+    return_number VARCHAR(50) NOT NULL,
+    customer_id UUID NOT NULL,
+    order_id UUID,
+    status VARCHAR(20) NOT NULL CHECK (status IN ('requested', 'received', 'inspected', 'cancelled')),
+    quarantine_location_id UUID,
+    notes TEXT,
+    created_by UUID NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    received_by UUID,
+    received_at TIMESTAMPTZ,
+    completed_at TIMESTAMPTZ,
+    UNIQUE (tenant_id, return_number)
+);
+
+CREATE INDEX IF NOT EXISTS idx_return_orders_tenant
+    ON public.return_orders(tenant_id, created_at DESC);
+
+CREATE INDEX IF NOT EXISTS idx_return_orders_customer
+    ON public.return_orders(tenant_id, customer_id);
+
+CREATE TABLE IF NOT EXISTS public.return_order_lines (
+    id UUID PRIMARY KEY,
+    tenant_id UUID NOT NULL,
+    return_order_id UUID NOT NULL REFERENCES public.return_orders(id) ON DELETE CASCADE,
+    line_number INTEGER NOT NULL,
+    product_id UUID NOT NULL,
+    quantity INTEGER NOT NULL CHECK (quantity > 0),
+    reason VARCHAR(30) NOT NULL,
+    note TEXT,
+    disposition VARCHAR(30) CHECK (disposition IN ('restock', 'refurbish', 'scrap', 'return_to_supplier')),
+    target_location_id UUID,
+    supplier_id UUID,
+    disposition_note TEXT,
+    inspected_by UUID,
+    inspected_at TIMESTAMPTZ,
+    UNIQUE (return_order_id, line_number)
+);
+
+CREATE INDEX IF NOT EXISTS idx_return_order_lines_order
+    ON public.return_order_lines(tenant_id, return_order_id);
+
+CREATE INDEX IF NOT EXISTS idx_return_order_lines_product
+    ON public.return_order_lines(tenant_id, product_id);
@@ -0,0 +1,18 @@
+-- What-if forecast scenarios
+-- The request (uplifts) and the full projection are stored together so a
+-- scenario can later be compared with the demand that actually happened.
+
+CREATE TABLE IF NOT EXISTS public.forecast_scenarios (
+    id UUID PRIMARY KEY,
+    tenant_id UUID NOT NULL,
+    name VARCHAR(200) NOT NULL,
+    start_date DATE NOT NULL,
+    end_date DATE NOT NULL,
+    scenario JSONB NOT NULL,
+    created_by UUID,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (start_date <= end_date)
+);
+
+CREATE INDEX IF NOT EXISTS idx_forecast_scenarios_tenant
+    ON public.forecast_scenarios(tenant_id, created_at DESC);
@@ -0,0 +1,107 @@
+-- Tenant-wide barcode uniqueness across products and variants
+-- Variants have no tenant_id and live in a different table than products, so a
+-- plain unique index cannot span both. Every barcode is mirrored into
+-- product_barcodes by trigger, and the unique index lives there.
+-- Existing duplicates are not backfilled (the first one wins) and invalid values
+-- are left alone; GET /api/v1/products/barcodes/report lists both.
+
+-- Must match product::barcode::uniqueness_key(): GTINs are zero-padded to 14
+-- digits so an UPC-A and the same EAN-13 with a leading zero collide
+CREATE OR REPLACE FUNCTION public.barcode_uniqueness_key(value TEXT) RETURNS TEXT AS $$
+    SELECT CASE
+        WHEN stripped ~ '^([0-9]{8}|[0-9]{12,14})$' THEN lpad(stripped, 14, '0')
+        ELSE stripped
+    END
+    FROM (SELECT regexp_replace(value, '\s', '', 'g') AS stripped) s;
+$$ LANGUAGE sql IMMUTABLE;
+
+CREATE TABLE IF NOT EXISTS public.product_barcodes (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    tenant_id UUID NOT NULL,
+    barcode_key VARCHAR(100) NOT NULL,
+    barcode VARCHAR(100) NOT NULL,
+    product_id UUID NOT NULL,
+    variant_id UUID,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_product_barcodes_tenant_key
+    ON public.product_barcodes(tenant_id, barcode_key);
+CREATE UNIQUE INDEX IF NOT EXISTS idx_product_barcodes_product
+    ON public.product_barcodes(product_id) WHERE variant_id IS NULL;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_product_barcodes_variant
+    ON public.product_barcodes(variant_id) WHERE variant_id IS NOT NULL;
+
+DO $$
+BEGIN
+    IF to_regclass('public.product_variants') IS NOT NULL THEN
+        ALTER TABLE public.product_variants ADD COLUMN IF NOT EXISTS barcode VARCHAR(100);
+    END IF;
+END;
+$$;
+
+-- Keeps product_barcodes in step; a duplicate raises unique_violation (23505)
+-- and aborts the write to products or product_variants
+CREATE OR REPLACE FUNCTION public.sync_product_barcode() RETURNS TRIGGER AS $$
+DECLARE
+    owner_tenant UUID;
+    owner_product UUID;
+    owner_variant UUID;
+BEGIN
+    IF TG_ARGV[0] = 'variant' THEN
+        owner_variant := COALESCE(NEW.id, OLD.id);
+        owner_product := COALESCE(NEW.product_id, OLD.product_id);
+        SELECT tenant_id INTO owner_tenant FROM public.products WHERE id = owner_product;
+    ELSE
+        owner_product := COALESCE(NEW.id, OLD.id);
+        owner_tenant := COALESCE(NEW.tenant_id, OLD.tenant_id);
+    END IF;
+
+    IF TG_OP = 'UPDATE' AND NEW.barcode IS NOT DISTINCT FROM OLD.barcode THEN
+        RETURN NULL;
+    END IF;
+
+    DELETE FROM public.product_barcodes
+    WHERE product_id = owner_product
+      AND variant_id IS NOT DISTINCT FROM owner_variant;
+
+    IF TG_OP <> 'DELETE' AND NULLIF(NEW.barcode, '') IS NOT NULL AND owner_tenant IS NOT NULL THEN
+        INSERT INTO public.product_barcodes (tenant_id, barcode_key, barcode, product_id, variant_id)
+        VALUES (owner_tenant, public.barcode_uniqueness_key(NEW.barcode), NEW.barcode, owner_product, owner_variant);
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DO $$
+BEGIN
+    IF to_regclass('public.products') IS NOT NULL THEN
+        INSERT INTO public.product_barcodes (tenant_id, barcode_key, barcode, product_id)
+        SELECT tenant_id, public.barcode_uniqueness_key(barcode), barcode, id
+        FROM public.products
+        WHERE NULLIF(barcode, '') IS NOT NULL
+        ORDER BY created_at
+        ON CONFLICT DO NOTHING;
+
+        DROP TRIGGER IF EXISTS trg_products_barcode ON public.products;
+        CREATE TRIGGER trg_products_barcode
+            AFTER INSERT OR UPDATE OF barcode OR DELETE ON public.products
+            FOR EACH ROW EXECUTE FUNCTION public.sync_product_barcode('product');
+    END IF;
+
+    IF to_regclass('public.product_variants') IS NOT NULL THEN
+        INSERT INTO public.product_barcodes (tenant_id, barcode_key, barcode, product_id, variant_id)
+        SELECT p.tenant_id, public.barcode_uniqueness_key(v.barcode), v.barcode, v.product_id, v.id
+        FROM public.product_variants v
+        JOIN public.products p ON p.id = v.product_id
+        WHERE NULLIF(v.barcode, '') IS NOT NULL
+        ORDER BY v.created_at
+        ON CONFLICT DO NOTHING;
+
+        DROP TRIGGER IF EXISTS trg_product_variants_barcode ON public.product_variants;
+        CREATE TRIGGER trg_product_variants_barcode
+            AFTER INSERT OR UPDATE OF barcode OR DELETE ON public.product_variants
+            FOR EACH ROW EXECUTE FUNCTION public.sync_product_barcode('variant');
+    END IF;
+END;
+$$;
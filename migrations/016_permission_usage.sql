@@ -0,0 +1,13 @@
+-- Daily permission usage for least-privilege reviews
+-- Counted in Redis during the day and flushed here once the day is over.
+-- override_uses are accesses made while an administrator impersonated the user.
+
+CREATE TABLE IF NOT EXISTS public.permission_usage_daily (
+    tenant_id UUID NOT NULL,
+    user_id UUID NOT NULL,
+    permission VARCHAR(255) NOT NULL,
+    usage_date DATE NOT NULL,
+    uses BIGINT NOT NULL DEFAULT 0,
+    override_uses BIGINT NOT NULL DEFAULT 0,
+    PRIMARY KEY (tenant_id, usage_date, user_id, permission)
+);
@@ -0,0 +1,33 @@
+-- Tenant activity feed
+-- A read model projected from audit events, customer events and inventory
+-- transactions, normalized to one row per source event. (source, source_id)
+-- is unique so replaying a source after a crash inserts nothing twice.
+-- activity_projection_marks holds the high-water mark of each source.
+
+CREATE TABLE IF NOT EXISTS public.tenant_activity (
+    id BIGSERIAL PRIMARY KEY,
+    tenant_id UUID NOT NULL,
+    source VARCHAR(20) NOT NULL CHECK (source IN ('audit', 'customer', 'inventory')),
+    source_id VARCHAR(255) NOT NULL,
+    actor_id UUID,
+    verb VARCHAR(100) NOT NULL,
+    object_type VARCHAR(100) NOT NULL,
+    object_id VARCHAR(255),
+    summary TEXT NOT NULL,
+    severity VARCHAR(20) NOT NULL CHECK (severity IN ('info', 'warning', 'critical')),
+    occurred_at TIMESTAMPTZ NOT NULL,
+    projected_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (source, source_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_tenant_activity_feed
+    ON public.tenant_activity(tenant_id, occurred_at DESC, id DESC);
+CREATE INDEX IF NOT EXISTS idx_tenant_activity_actor
+    ON public.tenant_activity(tenant_id, actor_id, occurred_at DESC);
+
+CREATE TABLE IF NOT EXISTS public.activity_projection_marks (
+    source VARCHAR(20) PRIMARY KEY,
+    position TIMESTAMPTZ NOT NULL,
+    source_id VARCHAR(255) NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,46 @@
+-- Price lists per customer group and sales channel
+-- A list applies when its channel and customer group are unset or match the
+-- request, it is in the requested currency and valid at the time (valid_until
+-- exclusive). The highest priority wins; ties between overlapping lists are
+-- rejected by the application on save.
+
+CREATE TABLE IF NOT EXISTS public.price_lists (
+    id UUID PRIMARY KEY,
+    tenant_id UUID NOT NULL,
+    name VARCHAR(255) NOT NULL,
+    currency CHAR(3) NOT NULL,
+    channel VARCHAR(100),
+    customer_group VARCHAR(100),
+    priority INTEGER NOT NULL DEFAULT 0,
+    valid_from TIMESTAMPTZ,
+    valid_until TIMESTAMPTZ,
+    is_active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    created_by UUID NOT NULL,
+    updated_by UUID NOT NULL,
+    CHECK (valid_until IS NULL OR valid_from IS NULL OR valid_until > valid_from)
+);
+
+CREATE INDEX IF NOT EXISTS idx_price_lists_tenant
+    ON public.price_lists (tenant_id, priority DESC);
+
+-- One line per product, or per variant (variant_id set), and list.
+-- Exactly one of fixed_price (cents) and discount_percent is set.
+CREATE TABLE IF NOT EXISTS public.price_list_lines (
+    price_list_id UUID NOT NULL REFERENCES public.price_lists(id) ON DELETE CASCADE,
+    product_id UUID NOT NULL,
+    variant_id UUID,
+    fixed_price BIGINT,
+    discount_percent DOUBLE PRECISION,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK ((fixed_price IS NULL) <> (discount_percent IS NULL)),
+    CHECK (fixed_price IS NULL OR fixed_price >= 0),
+    CHECK (discount_percent IS NULL OR discount_percent BETWEEN 0 AND 100)
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_price_list_lines_target
+    ON public.price_list_lines (price_list_id, product_id, COALESCE(variant_id, '00000000-0000-0000-0000-000000000000'::uuid));
+
+CREATE INDEX IF NOT EXISTS idx_price_list_lines_product
+    ON public.price_list_lines (product_id);
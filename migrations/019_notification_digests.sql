@@ -0,0 +1,41 @@
+-- Notification digests
+-- notification_preferences.delivery is the user's choice per category between
+-- an email right away and the daily digest (NULL: the configured default).
+-- Digest notifications wait in pending_digest_notifications until a digest
+-- run marks them sent; only rows with sent_at IS NULL are ever sent, so a
+-- resumed run never repeats an item. notification_digest_runs holds one row
+-- per tenant and local date: a run claims it, completes it when every user
+-- was handled, and a claim that never completed is taken over once stale.
+
+ALTER TABLE public.notification_preferences
+    ADD COLUMN IF NOT EXISTS delivery VARCHAR(20) CHECK (delivery IN ('immediate', 'digest'));
+
+CREATE TABLE IF NOT EXISTS public.pending_digest_notifications (
+    id BIGSERIAL PRIMARY KEY,
+    tenant_id UUID NOT NULL,
+    user_id UUID NOT NULL,
+    category VARCHAR(50) NOT NULL,
+    severity VARCHAR(20) NOT NULL CHECK (severity IN ('info', 'warning', 'critical')),
+    title TEXT NOT NULL,
+    body TEXT,
+    location_id UUID,
+    location_name VARCHAR(255),
+    customer_id UUID,
+    customer_name VARCHAR(255),
+    link TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    sent_at TIMESTAMPTZ,
+    digest_date DATE
+);
+
+CREATE INDEX IF NOT EXISTS idx_pending_digest_notifications_unsent
+    ON public.pending_digest_notifications(tenant_id, id)
+    WHERE sent_at IS NULL;
+
+CREATE TABLE IF NOT EXISTS public.notification_digest_runs (
+    tenant_id UUID NOT NULL,
+    digest_date DATE NOT NULL,
+    started_at TIMESTAMPTZ NOT NULL,
+    completed_at TIMESTAMPTZ,
+    PRIMARY KEY (tenant_id, digest_date)
+);
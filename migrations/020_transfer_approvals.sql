@@ -0,0 +1,53 @@
+-- Stock transfer approvals
+-- Each tenant keeps its approval rules as one JSON array (value thresholds and
+-- region pairs, see erp_master_data::inventory::transfer_approval). A transfer
+-- matching a rule starts in pending_approval with its stock already reserved
+-- and gets a transfer_approvals row; the decision is recorded on that row.
+-- escalated_at is set once when an undecided approval was escalated.
+
+DO $$
+BEGIN
+    IF EXISTS (SELECT 1 FROM pg_type WHERE typname = 'transfer_status') THEN
+        ALTER TYPE transfer_status ADD VALUE IF NOT EXISTS 'pending_approval';
+        ALTER TYPE transfer_status ADD VALUE IF NOT EXISTS 'pending';
+        ALTER TYPE transfer_status ADD VALUE IF NOT EXISTS 'rejected';
+        ALTER TYPE transfer_status ADD VALUE IF NOT EXISTS 'in_transit';
+        ALTER TYPE transfer_status ADD VALUE IF NOT EXISTS 'partially_received';
+        ALTER TYPE transfer_status ADD VALUE IF NOT EXISTS 'completed';
+    END IF;
+END
+$$;
+
+-- Region a location belongs to, matched by region-pair rules
+ALTER TABLE public.locations
+    ADD COLUMN IF NOT EXISTS region VARCHAR(100);
+
+CREATE TABLE IF NOT EXISTS public.transfer_approval_rules (
+    tenant_id UUID PRIMARY KEY,
+    rules JSONB NOT NULL DEFAULT '[]',
+    updated_by UUID NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS public.transfer_approvals (
+    transfer_id UUID PRIMARY KEY,
+    tenant_id UUID NOT NULL,
+    reasons TEXT[] NOT NULL,
+    transfer_value DOUBLE PRECISION NOT NULL,
+    requested_by UUID NOT NULL,
+    requested_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    escalated_at TIMESTAMPTZ,
+    decision VARCHAR(20) CHECK (decision IN ('approved', 'rejected')),
+    decided_by UUID,
+    decided_at TIMESTAMPTZ,
+    comment TEXT,
+    CHECK (decided_by IS NULL OR decided_by <> requested_by)
+);
+
+CREATE INDEX IF NOT EXISTS idx_transfer_approvals_tenant
+    ON public.transfer_approvals (tenant_id, requested_at DESC);
+
+-- Escalation scans undecided approvals of all tenants
+CREATE INDEX IF NOT EXISTS idx_transfer_approvals_undecided
+    ON public.transfer_approvals (requested_at)
+    WHERE decision IS NULL AND escalated_at IS NULL;
@@ -0,0 +1,31 @@
+-- Customer field encryption
+-- tax_numbers and bank_accounts hold either the plain JSON value or, once
+-- sealed, an envelope {"$encrypted": ..., "masked": ...} (see
+-- erp_master_data::customer::field_encryption). Exact-match lookups go
+-- through customer_blind_indexes, one HMAC per identifier, so searching never
+-- decrypts. customer_encryption_backfill records how far the backfill got per
+-- current key, so a restarted backfill resumes after last_customer_id.
+
+ALTER TABLE public.customers
+    ADD COLUMN IF NOT EXISTS bank_accounts JSONB DEFAULT '[]';
+
+CREATE TABLE IF NOT EXISTS public.customer_blind_indexes (
+    tenant_id UUID NOT NULL,
+    customer_id UUID NOT NULL REFERENCES public.customers(id) ON DELETE CASCADE,
+    field VARCHAR(50) NOT NULL,
+    blind_index CHAR(64) NOT NULL,
+    PRIMARY KEY (customer_id, field, blind_index)
+);
+
+CREATE INDEX IF NOT EXISTS idx_customer_blind_indexes_lookup
+    ON public.customer_blind_indexes(tenant_id, field, blind_index);
+
+CREATE TABLE IF NOT EXISTS public.customer_encryption_backfill (
+    job VARCHAR(100) PRIMARY KEY,
+    last_customer_id UUID,
+    rows_scanned BIGINT NOT NULL DEFAULT 0,
+    rows_encrypted BIGINT NOT NULL DEFAULT 0,
+    started_at TIMESTAMPTZ NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL,
+    completed_at TIMESTAMPTZ
+);
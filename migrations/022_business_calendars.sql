@@ -0,0 +1,24 @@
+-- Business calendars
+-- business_calendars holds each tenant's working weekdays as ISO numbers
+-- (1 = Monday ... 7 = Sunday) and the country its holidays were imported
+-- from; tenants without a row work every day. business_calendar_days holds
+-- the dated exceptions, one per tenant and date: holidays (working_day false)
+-- and working days on otherwise free dates. Imported rows are refreshed by
+-- later imports, manual rows are never replaced by one.
+
+CREATE TABLE IF NOT EXISTS public.business_calendars (
+    tenant_id UUID PRIMARY KEY,
+    working_days SMALLINT[] NOT NULL DEFAULT '{1,2,3,4,5}',
+    country VARCHAR(2),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS public.business_calendar_days (
+    tenant_id UUID NOT NULL,
+    day DATE NOT NULL,
+    label VARCHAR(100) NOT NULL,
+    working_day BOOLEAN NOT NULL DEFAULT false,
+    source VARCHAR(20) NOT NULL CHECK (source IN ('imported', 'manual')),
+    country VARCHAR(2),
+    PRIMARY KEY (tenant_id, day)
+);
@@ -0,0 +1,26 @@
+-- Customer portal accounts
+-- Binds a tenant user to the customer whose contact was invited. The user
+-- row itself lives in the tenant schema; this table decides that its tokens
+-- are portal tokens and which customers they can see. Invited accounts have
+-- no password until the invitation is accepted; revoked accounts cannot
+-- sign in again and are not turned back into regular users.
+
+CREATE TABLE IF NOT EXISTS public.customer_portal_users (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    tenant_id UUID NOT NULL,
+    user_id UUID NOT NULL,
+    customer_id UUID NOT NULL,
+    contact_id UUID,
+    email VARCHAR(255) NOT NULL,
+    include_children BOOLEAN NOT NULL DEFAULT false,
+    status VARCHAR(20) NOT NULL DEFAULT 'invited' CHECK (status IN ('invited', 'active', 'revoked')),
+    invited_by UUID,
+    invited_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    accepted_at TIMESTAMPTZ,
+    revoked_by UUID,
+    revoked_at TIMESTAMPTZ,
+    UNIQUE (tenant_id, user_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_customer_portal_users_customer
+    ON public.customer_portal_users (tenant_id, customer_id);
@@ -0,0 +1,29 @@
+-- Reservation drift corrections
+-- location_items.quantity_reserved is reconciled with the stock actually
+-- held for the item: active stock_reservations plus unshipped transfers (see
+-- erp_master_data::inventory::reservation_drift). Every correction the
+-- reconciliation makes is recorded here with the value it replaced; the
+-- column is never corrected without a row. corrected_by is NULL for the
+-- nightly job.
+
+CREATE TABLE IF NOT EXISTS public.reservation_adjustments (
+    id UUID PRIMARY KEY,
+    tenant_id UUID NOT NULL,
+    location_item_id UUID NOT NULL,
+    product_id UUID NOT NULL,
+    location_id UUID NOT NULL,
+    previous_reserved INTEGER NOT NULL,
+    corrected_reserved INTEGER NOT NULL CHECK (corrected_reserved >= 0),
+    run_id UUID NOT NULL,
+    corrected_by UUID,
+    corrected_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (corrected_reserved <> previous_reserved)
+);
+
+CREATE INDEX IF NOT EXISTS idx_reservation_adjustments_tenant
+    ON public.reservation_adjustments (tenant_id, corrected_at DESC);
+
+-- Repeated-drift alerts look at the recent corrections of one item
+CREATE INDEX IF NOT EXISTS idx_reservation_adjustments_item
+    ON public.reservation_adjustments (location_item_id, corrected_at DESC);
+
@@ -0,0 +1,46 @@
+-- Sandbox tenants
+-- A sandbox tenant holds test data only and is reset every night to its
+-- golden snapshot (see erp_core::sandbox). The snapshot keeps the rows of
+-- every table of the tenant schema as JSON, with the column types they were
+-- read with, and the tenant's settings. There is at most one snapshot per
+-- tenant; taking a new one replaces it.
+
+ALTER TABLE public.tenants
+    ADD COLUMN IF NOT EXISTS sandbox BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE TABLE IF NOT EXISTS public.sandbox_snapshots (
+    id UUID PRIMARY KEY,
+    tenant_id UUID NOT NULL UNIQUE REFERENCES public.tenants(id) ON DELETE CASCADE,
+    schema_name VARCHAR(100) NOT NULL,
+    tenant_settings JSONB NOT NULL DEFAULT '{}',
+    table_count INTEGER NOT NULL DEFAULT 0,
+    row_count BIGINT NOT NULL DEFAULT 0,
+    taken_by VARCHAR(255) NOT NULL,
+    taken_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS public.sandbox_snapshot_tables (
+    snapshot_id UUID NOT NULL REFERENCES public.sandbox_snapshots(id) ON DELETE CASCADE,
+    table_name VARCHAR(63) NOT NULL,
+    columns JSONB NOT NULL,
+    rows JSONB NOT NULL,
+    row_count BIGINT NOT NULL,
+    PRIMARY KEY (snapshot_id, table_name)
+);
+
+-- One row per nightly reset, failed ones included
+CREATE TABLE IF NOT EXISTS public.sandbox_resets (
+    id UUID PRIMARY KEY,
+    tenant_id UUID NOT NULL REFERENCES public.tenants(id) ON DELETE CASCADE,
+    snapshot_id UUID,
+    status VARCHAR(20) NOT NULL CHECK (status IN ('succeeded', 'failed')),
+    tables_restored INTEGER NOT NULL DEFAULT 0,
+    rows_restored BIGINT NOT NULL DEFAULT 0,
+    sessions_revoked INTEGER NOT NULL DEFAULT 0,
+    error TEXT,
+    started_at TIMESTAMPTZ NOT NULL,
+    finished_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_sandbox_resets_tenant
+    ON public.sandbox_resets (tenant_id, started_at DESC);
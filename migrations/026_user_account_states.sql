@@ -0,0 +1,27 @@
+-- User account states
+-- A user is active, deactivated (is_active = false), soft-deleted
+-- (deleted_at set) or locked (locked_until in the future). Deactivated and
+-- soft-deleted users can be reactivated while the tenant has a free seat:
+-- seat_limit caps the active, not deleted users of a tenant, NULL means no
+-- limit.
+
+ALTER TABLE public.tenants
+    ADD COLUMN IF NOT EXISTS seat_limit INTEGER CHECK (seat_limit > 0);
+
+ALTER TABLE IF EXISTS public.users
+    ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ;
+
+-- Tenant schemas created before this migration have no deleted_at
+DO $$
+DECLARE
+    tenant_schema TEXT;
+BEGIN
+    FOR tenant_schema IN
+        SELECT t.schema_name
+        FROM public.tenants t
+        JOIN information_schema.tables c
+          ON c.table_schema = t.schema_name AND c.table_name = 'users'
+    LOOP
+        EXECUTE format('ALTER TABLE %I.users ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ', tenant_schema);
+    END LOOP;
+END $$;
@@ -0,0 +1,63 @@
+-- Pick waves
+-- Where a product is stored within a location: zone (aisle or area) and bin.
+-- Both are optional; pick paths put items without them last. A wave groups
+-- active stock_reservations of one location into one pick round (see
+-- erp_master_data::inventory::picking). A reservation is in at most one open
+-- wave; closing books each confirmed line as a shipment and fulfils its
+-- reservation, unconfirmed lines are released.
+
+ALTER TABLE public.location_items
+    ADD COLUMN IF NOT EXISTS zone_code VARCHAR(50),
+    ADD COLUMN IF NOT EXISTS bin_code VARCHAR(50);
+
+CREATE TABLE IF NOT EXISTS public.pick_waves (
+    id UUID PRIMARY KEY,
+    tenant_id UUID NOT NULL,
+    wave_number VARCHAR(50) NOT NULL,
+    location_id UUID NOT NULL,
+    status VARCHAR(20) NOT NULL CHECK (status IN ('open', 'closed')),
+    path_strategy VARCHAR(50) NOT NULL,
+    cutoff_at TIMESTAMPTZ,
+    assigned_to UUID,
+    created_by UUID NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    closed_by UUID,
+    closed_at TIMESTAMPTZ,
+    UNIQUE (tenant_id, wave_number)
+);
+
+CREATE INDEX IF NOT EXISTS idx_pick_waves_tenant
+    ON public.pick_waves (tenant_id, created_at DESC);
+
+-- Pick lists of a picker
+CREATE INDEX IF NOT EXISTS idx_pick_waves_assigned
+    ON public.pick_waves (tenant_id, assigned_to)
+    WHERE status = 'open';
+
+CREATE TABLE IF NOT EXISTS public.pick_lines (
+    id UUID PRIMARY KEY,
+    tenant_id UUID NOT NULL,
+    wave_id UUID NOT NULL REFERENCES public.pick_waves(id) ON DELETE CASCADE,
+    sequence INTEGER NOT NULL,
+    reservation_id UUID NOT NULL,
+    product_id UUID NOT NULL,
+    location_item_id UUID NOT NULL,
+    zone_code VARCHAR(50),
+    bin_code VARCHAR(50),
+    reference_number VARCHAR(100),
+    requested_quantity INTEGER NOT NULL CHECK (requested_quantity > 0),
+    picked_quantity INTEGER CHECK (picked_quantity >= 0 AND picked_quantity <= requested_quantity),
+    status VARCHAR(20) NOT NULL CHECK (status IN ('open', 'picked', 'short', 'released')),
+    short_reason TEXT,
+    short_resolution VARCHAR(20) CHECK (short_resolution IN ('reallocated', 'backordered')),
+    reallocated_location_id UUID,
+    reallocated_reservation_id UUID,
+    confirmed_by UUID,
+    confirmed_at TIMESTAMPTZ,
+    UNIQUE (wave_id, sequence)
+);
+
+-- Reservations already in an open wave are skipped when selecting
+CREATE INDEX IF NOT EXISTS idx_pick_lines_reservation
+    ON public.pick_lines (reservation_id);
@@ -0,0 +1,28 @@
+-- Export letterheads
+-- tenant_letterheads holds the legal entity details printed at the top of a
+-- tenant's exports; only the legal name is required. Every change is kept in
+-- tenant_letterhead_changes with the previous and the new values and the
+-- user who made it, so the header of an old export can be explained later.
+
+CREATE TABLE IF NOT EXISTS public.tenant_letterheads (
+    tenant_id UUID PRIMARY KEY,
+    legal_name VARCHAR(200) NOT NULL,
+    registration_number VARCHAR(50),
+    vat_id VARCHAR(15),
+    registered_address JSONB,
+    logo_ref VARCHAR(500),
+    updated_by UUID,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS public.tenant_letterhead_changes (
+    id BIGSERIAL PRIMARY KEY,
+    tenant_id UUID NOT NULL,
+    previous JSONB,
+    current JSONB NOT NULL,
+    changed_by UUID,
+    changed_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_tenant_letterhead_changes_tenant
+    ON public.tenant_letterhead_changes (tenant_id, changed_at DESC);
@@ -0,0 +1,34 @@
+-- Document number sequences
+-- number_sequences holds the next number of each tenant sequence. Sequences
+-- flagged no_gaps are drawn one number at a time inside the creating
+-- transaction; the others are leased in blocks per API instance.
+-- number_sequence_blocks records every leased block: 'leased' while an
+-- instance hands it out, 'returned' when its unused rest was given back on
+-- shutdown (leased again before the sequence advances), 'consumed' when done.
+-- A block left 'leased' by an instance that died has lapsed: its unused
+-- numbers are gaps.
+
+CREATE TABLE IF NOT EXISTS public.number_sequences (
+    tenant_id UUID NOT NULL,
+    sequence VARCHAR(100) NOT NULL,
+    next_value BIGINT NOT NULL DEFAULT 1,
+    no_gaps BOOLEAN NOT NULL DEFAULT false,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (tenant_id, sequence)
+);
+
+CREATE TABLE IF NOT EXISTS public.number_sequence_blocks (
+    id BIGSERIAL PRIMARY KEY,
+    tenant_id UUID NOT NULL,
+    sequence VARCHAR(100) NOT NULL,
+    first_value BIGINT NOT NULL,
+    last_value BIGINT NOT NULL CHECK (last_value >= first_value),
+    state VARCHAR(20) NOT NULL CHECK (state IN ('leased', 'returned', 'consumed')),
+    instance_id VARCHAR(200),
+    leased_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    released_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_number_sequence_blocks_open
+    ON public.number_sequence_blocks (tenant_id, sequence, first_value)
+    WHERE state IN ('leased', 'returned');
@@ -0,0 +1,95 @@
+-- Customer credit standing for finance integrations
+-- Until invoicing exists the external accounting system pushes each
+-- customer's open items: receivables with their due date and open orders
+-- holding credit ('reservation'). Items are keyed per tenant by the external
+-- document number; sending a document again updates it in place.
+-- customer_credit_standing holds what the imports computed for the
+-- customer, including the credit status they set and, for an automatic hold,
+-- the status to return to once the overdue balance is settled. The row is
+-- only rewritten when the standing actually changes.
+-- customer_credit_feed is served through the change log as entity type
+-- 'customer_credit': the triggers below log a change whenever a customer's
+-- standing, credit limit or credit status changes.
+
+CREATE TABLE IF NOT EXISTS public.customer_open_items (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    tenant_id UUID NOT NULL,
+    customer_id UUID NOT NULL REFERENCES public.customers(id) ON DELETE CASCADE,
+    external_document_number VARCHAR(100) NOT NULL,
+    kind VARCHAR(20) NOT NULL CHECK (kind IN ('receivable', 'reservation')),
+    document_date DATE,
+    due_date DATE,
+    amount NUMERIC(15, 2) NOT NULL,
+    open_amount NUMERIC(15, 2) NOT NULL,
+    currency CHAR(3),
+    imported_by UUID,
+    imported_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (tenant_id, external_document_number)
+);
+
+CREATE INDEX IF NOT EXISTS idx_customer_open_items_customer
+    ON public.customer_open_items(tenant_id, customer_id);
+
+CREATE TABLE IF NOT EXISTS public.customer_credit_standing (
+    customer_id UUID PRIMARY KEY REFERENCES public.customers(id) ON DELETE CASCADE,
+    tenant_id UUID NOT NULL,
+    credit_status VARCHAR(30) NOT NULL,
+    status_before_hold VARCHAR(30),
+    booked_exposure NUMERIC(15, 2) NOT NULL DEFAULT 0,
+    active_reservations NUMERIC(15, 2) NOT NULL DEFAULT 0,
+    overdue_balance NUMERIC(15, 2) NOT NULL DEFAULT 0,
+    days_past_due INTEGER NOT NULL DEFAULT 0,
+    status_changed_at TIMESTAMPTZ,
+    status_reason TEXT,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE OR REPLACE FUNCTION public.record_credit_standing_change() RETURNS TRIGGER AS $$
+BEGIN
+    INSERT INTO public.change_log (tenant_id, entity_type, entity_id, operation)
+    VALUES (NEW.tenant_id, 'customer_credit', NEW.customer_id, 'updated');
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS trg_customer_credit_standing_change_log ON public.customer_credit_standing;
+CREATE TRIGGER trg_customer_credit_standing_change_log
+    AFTER INSERT OR UPDATE ON public.customer_credit_standing
+    FOR EACH ROW EXECUTE FUNCTION public.record_credit_standing_change();
+
+-- credit_status and is_deleted are read through to_jsonb so older customer
+-- tables without them still migrate
+DROP TRIGGER IF EXISTS trg_customers_credit_change_log ON public.customers;
+CREATE TRIGGER trg_customers_credit_change_log
+    AFTER INSERT OR DELETE ON public.customers
+    FOR EACH ROW EXECUTE FUNCTION public.record_entity_change('customer_credit');
+
+DROP TRIGGER IF EXISTS trg_customers_credit_update_change_log ON public.customers;
+CREATE TRIGGER trg_customers_credit_update_change_log
+    AFTER UPDATE ON public.customers
+    FOR EACH ROW
+    WHEN (OLD.credit_limit IS DISTINCT FROM NEW.credit_limit
+        OR to_jsonb(OLD) ->> 'credit_status' IS DISTINCT FROM to_jsonb(NEW) ->> 'credit_status'
+        OR to_jsonb(OLD) ->> 'is_deleted' IS DISTINCT FROM to_jsonb(NEW) ->> 'is_deleted')
+    EXECUTE FUNCTION public.record_entity_change('customer_credit');
+
+CREATE OR REPLACE VIEW public.customer_credit_feed AS
+SELECT
+    c.id,
+    c.tenant_id,
+    c.customer_number,
+    c.credit_limit,
+    COALESCE(s.booked_exposure, 0) AS booked_exposure,
+    COALESCE(s.active_reservations, 0) AS active_reservations,
+    CASE WHEN c.credit_limit > 0 THEN
+        ROUND((COALESCE(s.booked_exposure, 0) + COALESCE(s.active_reservations, 0)) * 100 / c.credit_limit, 2)
+    END AS utilization_percent,
+    COALESCE(s.credit_status, to_jsonb(c) ->> 'credit_status', 'good') AS credit_status,
+    COALESCE(s.overdue_balance, 0) AS overdue_balance,
+    COALESCE(s.days_past_due, 0) AS days_past_due,
+    s.status_changed_at,
+    s.status_reason,
+    s.updated_at AS standing_updated_at
+FROM public.customers c
+LEFT JOIN public.customer_credit_standing s ON s.customer_id = c.id;
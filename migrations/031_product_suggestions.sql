@@ -0,0 +1,44 @@
+-- Product optimization suggestions awaiting review
+-- Suggestions the AI engine makes when a product is created or updated, or
+-- when a periodic job analyses the catalog, are stored here as 'pending'
+-- instead of being written to the product. A reviewer accepts them, which
+-- applies the suggested value through the regular product update, or rejects
+-- them with a reason. current_value and product_updated_at describe the
+-- product the suggestion was made for: once the product changed the
+-- suggestion is 'expired' instead of applied.
+-- product_suggestion_auto_apply is the per-tenant opt-in: suggestions of a
+-- listed type with at least min_confidence are applied right away and stored
+-- as 'accepted'. Without a row nothing is applied automatically.
+
+CREATE TABLE IF NOT EXISTS public.product_suggestions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    tenant_id UUID NOT NULL,
+    product_id UUID NOT NULL,
+    suggestion_type VARCHAR(100) NOT NULL,
+    rationale TEXT NOT NULL,
+    expected_impact TEXT,
+    current_value JSONB,
+    suggested_value JSONB NOT NULL,
+    confidence DOUBLE PRECISION NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'pending'
+        CHECK (status IN ('pending', 'accepted', 'rejected', 'expired')),
+    source VARCHAR(20) NOT NULL CHECK (source IN ('create', 'update', 'job')),
+    product_updated_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    decided_by UUID,
+    decided_at TIMESTAMPTZ,
+    rejection_reason TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_product_suggestions_pending
+    ON public.product_suggestions (tenant_id, product_id)
+    WHERE status = 'pending';
+
+CREATE TABLE IF NOT EXISTS public.product_suggestion_auto_apply (
+    tenant_id UUID NOT NULL,
+    suggestion_type VARCHAR(100) NOT NULL,
+    min_confidence DOUBLE PRECISION NOT NULL CHECK (min_confidence > 0 AND min_confidence <= 1),
+    updated_by UUID,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (tenant_id, suggestion_type)
+);
@@ -0,0 +1,32 @@
+-- Storage capacity of locations and bins
+-- A row with bin_code NULL is the capacity of the whole location, one with a
+-- bin_code that of a single bin. Receipts and transfers into a location with a
+-- capacity are checked against it: the stock at the location, stock of open
+-- transfers on their way there and the incoming quantity, converted into the
+-- capacity's unit. Locations without a row are not checked.
+-- product_space_factors holds explicit space per unit of a product; without
+-- one the space is derived from the product's dimensions, or estimated.
+
+CREATE TABLE IF NOT EXISTS public.location_capacities (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    tenant_id UUID NOT NULL,
+    location_id UUID NOT NULL REFERENCES public.locations(id) ON DELETE CASCADE,
+    bin_code VARCHAR(50),
+    unit VARCHAR(20) NOT NULL CHECK (unit IN ('pallet_positions', 'cubic_meters', 'units')),
+    capacity DOUBLE PRECISION NOT NULL CHECK (capacity > 0),
+    updated_by UUID,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_location_capacities_bin
+    ON public.location_capacities (tenant_id, location_id, COALESCE(bin_code, ''));
+
+CREATE TABLE IF NOT EXISTS public.product_space_factors (
+    tenant_id UUID NOT NULL,
+    product_id UUID NOT NULL,
+    unit VARCHAR(20) NOT NULL CHECK (unit IN ('pallet_positions', 'cubic_meters', 'units')),
+    space_per_unit DOUBLE PRECISION NOT NULL CHECK (space_per_unit > 0),
+    updated_by UUID,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (tenant_id, product_id, unit)
+);
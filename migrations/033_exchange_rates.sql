@@ -0,0 +1,38 @@
+-- Tenant base currency and exchange rates
+-- Monetary aggregates of the valuation, KPI, margin and dashboard reports are
+-- converted into the tenant's base currency with the rate effective on the
+-- valuation date: the latest rate of the pair whose effective_date is not
+-- after it. A rate converts one unit of from_currency into `rate` units of
+-- to_currency and is used in the other direction as its inverse.
+--
+-- Tenants enter their own rates (source 'manual'). The ECB fetcher publishes
+-- the euro reference rates for everyone (tenant_id NULL, source 'ecb'); they
+-- are only used where a tenant has no rate of its own.
+
+ALTER TABLE public.tenants
+    ADD COLUMN IF NOT EXISTS base_currency CHAR(3) NOT NULL DEFAULT 'USD';
+
+CREATE TABLE IF NOT EXISTS public.exchange_rates (
+    id UUID PRIMARY KEY,
+    tenant_id UUID,
+    from_currency CHAR(3) NOT NULL,
+    to_currency CHAR(3) NOT NULL,
+    rate NUMERIC(24,10) NOT NULL,
+    effective_date DATE NOT NULL,
+    source VARCHAR(20) NOT NULL DEFAULT 'manual',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    created_by UUID,
+    updated_by UUID,
+    CHECK (rate > 0),
+    CHECK (from_currency <> to_currency),
+    CHECK (source IN ('manual', 'ecb')),
+    CHECK ((tenant_id IS NULL) = (source = 'ecb'))
+);
+
+-- One rate per pair and day, per tenant or published
+CREATE UNIQUE INDEX IF NOT EXISTS idx_exchange_rates_pair_day
+    ON public.exchange_rates (COALESCE(tenant_id, '00000000-0000-0000-0000-000000000000'::uuid), from_currency, to_currency, effective_date);
+
+CREATE INDEX IF NOT EXISTS idx_exchange_rates_lookup
+    ON public.exchange_rates (from_currency, to_currency, effective_date DESC);
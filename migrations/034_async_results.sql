@@ -0,0 +1,25 @@
+-- Responses of requests answered asynchronously
+-- A request to a concurrency-limited endpoint sent with `Prefer: respond-async`
+-- is answered with 202 and runs once a slot of its endpoint group is free.
+-- Its response is kept here until expires_at and served to the same user at
+-- /api/v1/async-results/{id}.
+
+CREATE TABLE IF NOT EXISTS public.async_results (
+    id UUID PRIMARY KEY,
+    tenant_id UUID NOT NULL,
+    user_id UUID,
+    endpoint_group VARCHAR(50) NOT NULL,
+    method VARCHAR(10) NOT NULL,
+    path TEXT NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'pending',
+    status_code INTEGER,
+    content_type TEXT,
+    body BYTEA,
+    error TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    completed_at TIMESTAMPTZ,
+    expires_at TIMESTAMPTZ NOT NULL,
+    CHECK (status IN ('pending', 'completed', 'failed'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_async_results_expires_at ON public.async_results (expires_at);
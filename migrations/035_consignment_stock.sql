@@ -0,0 +1,43 @@
+-- Consignment stock
+-- Suppliers may keep stock of their own in our locations. It is part of
+-- location_items.quantity_available like any other unit, and consignment_stock
+-- holds how much of it belongs to which supplier; the rest is ours. Only our
+-- own stock is valued. Consumption (a sale, or taking units over into our own
+-- stock) ends the supplier's ownership and is priced from the supplier's
+-- catalog entry; consignment_movements is what settlement with the supplier
+-- is based on.
+
+CREATE TABLE IF NOT EXISTS public.consignment_stock (
+    tenant_id UUID NOT NULL,
+    supplier_id UUID NOT NULL,
+    product_id UUID NOT NULL,
+    location_id UUID NOT NULL REFERENCES public.locations(id) ON DELETE CASCADE,
+    quantity INTEGER NOT NULL CHECK (quantity >= 0),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (tenant_id, product_id, location_id, supplier_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_consignment_stock_supplier
+    ON public.consignment_stock (tenant_id, supplier_id);
+
+CREATE TABLE IF NOT EXISTS public.consignment_movements (
+    id UUID PRIMARY KEY,
+    tenant_id UUID NOT NULL,
+    supplier_id UUID NOT NULL,
+    product_id UUID NOT NULL,
+    location_id UUID NOT NULL,
+    movement VARCHAR(20) NOT NULL
+        CHECK (movement IN ('receipt', 'transfer_out', 'transfer_in', 'sale', 'transfer_to_own')),
+    quantity INTEGER NOT NULL CHECK (quantity > 0),
+    -- Consignment price of consumptions, in minor units of currency
+    unit_price BIGINT,
+    currency CHAR(3),
+    catalog_entry_id UUID,
+    reference TEXT,
+    created_by UUID NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (movement NOT IN ('sale', 'transfer_to_own') OR (unit_price IS NOT NULL AND currency IS NOT NULL))
+);
+
+CREATE INDEX IF NOT EXISTS idx_consignment_movements_supplier
+    ON public.consignment_movements (tenant_id, supplier_id, created_at);
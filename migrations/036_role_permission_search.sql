@@ -0,0 +1,30 @@
+-- Role and permission search
+-- The role editor searches roles by name and description and permissions by
+-- `resource:action description` with LIKE '%term%', which trigram indexes
+-- serve. Listing the roles that grant a permission reads role_permissions by
+-- permission_id, which its primary key (role_id, permission_id) does not cover.
+
+CREATE EXTENSION IF NOT EXISTS pg_trgm WITH SCHEMA public;
+
+-- Tenant schemas created before this migration have none of these indexes
+DO $$
+DECLARE
+    tenant_schema TEXT;
+BEGIN
+    FOR tenant_schema IN
+        SELECT t.schema_name
+        FROM public.tenants t
+        JOIN information_schema.tables c
+          ON c.table_schema = t.schema_name AND c.table_name = 'role_permissions'
+    LOOP
+        EXECUTE format(
+            'CREATE INDEX IF NOT EXISTS %I ON %I.roles USING GIN (lower(name || '' '' || coalesce(description, '''')) public.gin_trgm_ops)',
+            'idx_' || tenant_schema || '_roles_search', tenant_schema);
+        EXECUTE format(
+            'CREATE INDEX IF NOT EXISTS %I ON %I.permissions USING GIN (lower(resource || '':'' || action || '' '' || coalesce(description, '''')) public.gin_trgm_ops)',
+            'idx_' || tenant_schema || '_permissions_search', tenant_schema);
+        EXECUTE format(
+            'CREATE INDEX IF NOT EXISTS %I ON %I.role_permissions (permission_id)',
+            'idx_' || tenant_schema || '_role_permissions_permission_id', tenant_schema);
+    END LOOP;
+END $$;
@@ -0,0 +1,7 @@
+-- Expected arrival of stock transfers
+-- Available-to-promise counts an inbound transfer from the day the receiving
+-- location expects it. Transfers without an expected arrival are still listed
+-- by the calculation but not promised against.
+
+ALTER TABLE IF EXISTS stock_transfers
+    ADD COLUMN IF NOT EXISTS expected_arrival_date DATE;
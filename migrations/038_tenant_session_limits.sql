@@ -0,0 +1,13 @@
+-- Per-tenant session limit
+-- Caps how many sessions one user of the tenant may hold at once, and whether
+-- a login at the cap signs out the user's oldest session or is refused. A
+-- tenant without a row gets the built-in cap of 10 with evict_oldest.
+
+CREATE TABLE IF NOT EXISTS public.tenant_session_limits (
+    tenant_id UUID PRIMARY KEY,
+    max_sessions_per_user INTEGER NOT NULL CHECK (max_sessions_per_user BETWEEN 1 AND 1000),
+    limit_strategy VARCHAR(20) NOT NULL DEFAULT 'evict_oldest'
+        CHECK (limit_strategy IN ('evict_oldest', 'reject')),
+    updated_by UUID,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
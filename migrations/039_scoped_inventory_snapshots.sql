@@ -0,0 +1,55 @@
+-- Scoped inventory snapshots
+-- A snapshot is taken of one location, either of all its stock or of the
+-- products matching a scope (product ids, category, ABC class, lowest unit
+-- cost). Each snapshot is a row of inventory_snapshot_runs recording its
+-- scope, with one inventory_snapshots line per product it covers. Snapshots
+-- of different scopes are never compared.
+--
+-- inventory_snapshot_schedules holds the month-end snapshots a tenant wants
+-- taken per location, each with its scope.
+
+CREATE TABLE IF NOT EXISTS public.inventory_snapshot_runs (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    tenant_id UUID NOT NULL,
+    location_id UUID NOT NULL REFERENCES locations(id) ON DELETE CASCADE,
+    snapshot_date DATE NOT NULL,
+    -- '{}' for a snapshot of the whole location
+    scope JSONB NOT NULL DEFAULT '{}'::jsonb,
+    is_partial BOOLEAN NOT NULL DEFAULT FALSE,
+    trigger VARCHAR(20) NOT NULL DEFAULT 'manual' CHECK (trigger IN ('manual', 'month_end')),
+    line_count INTEGER NOT NULL DEFAULT 0,
+    -- Cents
+    total_value BIGINT NOT NULL DEFAULT 0,
+    created_by UUID,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_inventory_snapshot_runs_tenant_location
+    ON public.inventory_snapshot_runs (tenant_id, location_id, created_at DESC);
+
+ALTER TABLE inventory_snapshots
+    ADD COLUMN IF NOT EXISTS run_id UUID REFERENCES public.inventory_snapshot_runs(id) ON DELETE CASCADE,
+    ADD COLUMN IF NOT EXISTS category_id UUID,
+    ADD COLUMN IF NOT EXISTS abc_classification VARCHAR(1);
+
+-- Several snapshots of a location may be taken on one day, each of its own scope
+ALTER TABLE inventory_snapshots DROP CONSTRAINT IF EXISTS unique_snapshot_product_location_date;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_inventory_snapshots_run_product
+    ON inventory_snapshots (run_id, product_id) WHERE run_id IS NOT NULL;
+
+-- Scoped snapshots read only the filtered items of the location
+CREATE INDEX IF NOT EXISTS idx_location_items_location_abc
+    ON location_items (location_id, abc_classification) INCLUDE (product_id);
+CREATE INDEX IF NOT EXISTS idx_products_tenant_category_cost
+    ON products (tenant_id, category_id, cost_price);
+
+CREATE TABLE IF NOT EXISTS public.inventory_snapshot_schedules (
+    tenant_id UUID NOT NULL,
+    location_id UUID NOT NULL REFERENCES locations(id) ON DELETE CASCADE,
+    scope JSONB NOT NULL DEFAULT '{}'::jsonb,
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    last_run_on DATE,
+    updated_by UUID,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (tenant_id, location_id)
+);
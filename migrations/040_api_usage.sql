@@ -0,0 +1,22 @@
+-- API usage per tenant
+-- Calls with a valid access token, summed per day, token and endpoint group
+-- (the first segments of the path below /api/v1). Latency and bytes are
+-- totals, so averages stay exact when counts from several instances are
+-- added up. rate_limited counts the calls answered with 429.
+
+CREATE TABLE IF NOT EXISTS public.api_usage_daily (
+    tenant_id UUID NOT NULL,
+    usage_date DATE NOT NULL,
+    -- jti of the access token
+    token_id VARCHAR(64) NOT NULL,
+    user_id UUID NOT NULL,
+    endpoint_group VARCHAR(100) NOT NULL,
+    calls BIGINT NOT NULL DEFAULT 0,
+    rate_limited BIGINT NOT NULL DEFAULT 0,
+    total_latency_ms BIGINT NOT NULL DEFAULT 0,
+    bytes_transferred BIGINT NOT NULL DEFAULT 0,
+    PRIMARY KEY (tenant_id, usage_date, token_id, endpoint_group)
+);
+
+CREATE INDEX IF NOT EXISTS idx_api_usage_daily_token
+    ON public.api_usage_daily (tenant_id, token_id, usage_date);
@@ -0,0 +1,64 @@
+-- Product lifecycle stages and their automation
+-- product_lifecycles holds each product's current stage. The daily lifecycle
+-- job moves active products along launch -> growth -> maturity -> decline
+-- from their age and sales; stage_set_by_automation tells its changes from
+-- the ones made by hand, which it leaves alone for a freeze window.
+-- Every stage change is a row of product_lifecycle_transitions with who made
+-- it (manual, automation, or an accepted suggestion), the rule and the metric
+-- values it was made on.
+-- product_lifecycle_automation is the per-tenant mode: 'suggest' proposes
+-- transitions in the product suggestion review, 'auto' applies them, 'off'
+-- does neither. Without a row the configured default applies.
+
+CREATE TABLE IF NOT EXISTS public.product_lifecycles (
+    id UUID NOT NULL DEFAULT gen_random_uuid(),
+    tenant_id UUID NOT NULL,
+    product_id UUID NOT NULL,
+    stage VARCHAR(20) NOT NULL,
+    stage_changed_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    previous_stage VARCHAR(20),
+    stage_set_by_automation BOOLEAN NOT NULL DEFAULT FALSE,
+    stage_evidence JSONB,
+    launched_at TIMESTAMPTZ,
+    time_to_market INTEGER,
+    -- Cents
+    development_cost BIGINT,
+    total_revenue BIGINT NOT NULL DEFAULT 0,
+    units_produced INTEGER NOT NULL DEFAULT 0,
+    units_sold INTEGER NOT NULL DEFAULT 0,
+    planned_eol_date TIMESTAMPTZ,
+    replacement_product_id UUID,
+    sunset_strategy TEXT,
+    total_carbon_footprint DOUBLE PRECISION,
+    materials_recycled DOUBLE PRECISION,
+    waste_generated DOUBLE PRECISION,
+    notes TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    created_by UUID NOT NULL,
+    updated_by UUID NOT NULL,
+    PRIMARY KEY (tenant_id, product_id)
+);
+
+CREATE TABLE IF NOT EXISTS public.product_lifecycle_transitions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    tenant_id UUID NOT NULL,
+    product_id UUID NOT NULL,
+    from_stage VARCHAR(20),
+    to_stage VARCHAR(20) NOT NULL,
+    source VARCHAR(20) NOT NULL CHECK (source IN ('manual', 'automation', 'suggestion')),
+    rule VARCHAR(50),
+    evidence JSONB,
+    changed_by UUID,
+    changed_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_product_lifecycle_transitions_product
+    ON public.product_lifecycle_transitions (tenant_id, product_id, changed_at DESC);
+
+CREATE TABLE IF NOT EXISTS public.product_lifecycle_automation (
+    tenant_id UUID PRIMARY KEY,
+    mode VARCHAR(10) NOT NULL CHECK (mode IN ('off', 'suggest', 'auto')),
+    updated_by UUID,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
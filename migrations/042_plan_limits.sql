@@ -0,0 +1,32 @@
+-- Plan limits per tenant
+-- The data limits of the tenant's plan, next to seat_limit (the users limit);
+-- NULL means unlimited. With enforce_plan_limits set, creating customers or
+-- products at their limit is refused with PLAN_LIMIT_EXCEEDED; otherwise the
+-- limits are only measured and alerted on.
+-- tenant_usage_daily holds the nightly measurements: row counts are planner
+-- estimates, storage_bytes the size of the tenant schema.
+-- tenant_plan_limit_alerts remembers the highest threshold (percent) already
+-- notified per limit, so a threshold is notified once per crossing.
+
+ALTER TABLE public.tenants
+    ADD COLUMN IF NOT EXISTS customer_limit BIGINT CHECK (customer_limit > 0),
+    ADD COLUMN IF NOT EXISTS product_limit BIGINT CHECK (product_limit > 0),
+    ADD COLUMN IF NOT EXISTS storage_limit_bytes BIGINT CHECK (storage_limit_bytes > 0),
+    ADD COLUMN IF NOT EXISTS enforce_plan_limits BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE TABLE IF NOT EXISTS public.tenant_usage_daily (
+    tenant_id UUID NOT NULL,
+    usage_date DATE NOT NULL,
+    resource VARCHAR(20) NOT NULL CHECK (resource IN ('customers', 'products', 'storage_bytes', 'users')),
+    value BIGINT NOT NULL,
+    measured_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (tenant_id, usage_date, resource)
+);
+
+CREATE TABLE IF NOT EXISTS public.tenant_plan_limit_alerts (
+    tenant_id UUID NOT NULL,
+    resource VARCHAR(20) NOT NULL,
+    notified_percent INTEGER NOT NULL,
+    notified_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (tenant_id, resource)
+);
@@ -0,0 +1,25 @@
+-- Email template overrides
+-- Tenants may replace the subject and bodies of the built-in email templates
+-- (email_verification, password_reset, welcome, password_changed,
+-- invitation), per locale or as their default (locale NULL). Overrides are
+-- validated against the template's declared variables before they are stored.
+-- Emails are sent in the tenant's email_locale: its override for that locale,
+-- else its default, else the built-in wording.
+
+ALTER TABLE public.tenants
+    ADD COLUMN IF NOT EXISTS email_locale VARCHAR(16);
+
+CREATE TABLE IF NOT EXISTS public.email_template_overrides (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    tenant_id UUID NOT NULL,
+    template_name VARCHAR(64) NOT NULL,
+    locale VARCHAR(16),
+    subject TEXT NOT NULL,
+    html_body TEXT NOT NULL,
+    text_body TEXT NOT NULL,
+    updated_by UUID,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_email_template_overrides_template
+    ON public.email_template_overrides (tenant_id, template_name, (COALESCE(locale, '')));
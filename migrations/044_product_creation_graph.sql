@@ -0,0 +1,80 @@
+-- Rows a product is created with
+-- A new product is written in one transaction together with its attributes,
+-- its first analytics record and its lifecycle record (product_lifecycles,
+-- see 041). Products created before that could be left half-written:
+-- attribute and analytics rows whose product is gone, and products without a
+-- lifecycle record. The product integrity check finds those and repairs them.
+
+CREATE TABLE IF NOT EXISTS public.product_attributes (
+    id UUID NOT NULL DEFAULT gen_random_uuid(),
+    tenant_id UUID NOT NULL,
+    product_id UUID NOT NULL,
+    digital_twin_id VARCHAR(255),
+    iot_device_ids TEXT[],
+    three_d_model_url TEXT,
+    ar_model_url TEXT,
+    -- kg CO2 equivalent
+    carbon_footprint DOUBLE PRECISION,
+    recyclable_percentage DOUBLE PRECISION,
+    sustainability_rating VARCHAR(50),
+    eco_certifications TEXT[],
+    compliance_standards TEXT[],
+    quality_grade VARCHAR(50),
+    certification_expiry TIMESTAMPTZ,
+    batch_tracking_required BOOLEAN NOT NULL DEFAULT FALSE,
+    shelf_life_days INTEGER,
+    storage_temperature_min DOUBLE PRECISION,
+    storage_temperature_max DOUBLE PRECISION,
+    storage_humidity_max DOUBLE PRECISION,
+    hazardous_material BOOLEAN NOT NULL DEFAULT FALSE,
+    fragile BOOLEAN NOT NULL DEFAULT FALSE,
+    market_position VARCHAR(50),
+    competitor_products TEXT[],
+    market_share_percentage DOUBLE PRECISION,
+    ai_generated_description TEXT,
+    ai_tags TEXT[],
+    seo_keywords TEXT[],
+    blockchain_hash VARCHAR(255),
+    authenticity_token VARCHAR(255),
+    provenance_data JSONB,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    created_by UUID NOT NULL,
+    updated_by UUID NOT NULL,
+    PRIMARY KEY (tenant_id, product_id)
+);
+
+CREATE TABLE IF NOT EXISTS public.product_analytics (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    tenant_id UUID NOT NULL,
+    product_id UUID NOT NULL,
+    period_start TIMESTAMPTZ NOT NULL,
+    period_end TIMESTAMPTZ NOT NULL,
+    period_type VARCHAR(20) NOT NULL CHECK (period_type IN ('daily', 'weekly', 'monthly', 'quarterly')),
+    units_sold INTEGER NOT NULL DEFAULT 0,
+    -- Cents
+    revenue BIGINT NOT NULL DEFAULT 0,
+    gross_profit BIGINT NOT NULL DEFAULT 0,
+    profit_margin DOUBLE PRECISION NOT NULL DEFAULT 0,
+    stock_turns DOUBLE PRECISION NOT NULL DEFAULT 0,
+    days_of_inventory DOUBLE PRECISION NOT NULL DEFAULT 0,
+    stockout_days INTEGER NOT NULL DEFAULT 0,
+    excess_stock_value BIGINT NOT NULL DEFAULT 0,
+    return_rate DOUBLE PRECISION NOT NULL DEFAULT 0,
+    defect_rate DOUBLE PRECISION NOT NULL DEFAULT 0,
+    customer_satisfaction DOUBLE PRECISION,
+    quality_incidents INTEGER NOT NULL DEFAULT 0,
+    market_share DOUBLE PRECISION,
+    price_competitiveness DOUBLE PRECISION,
+    demand_forecast INTEGER,
+    trend_score DOUBLE PRECISION,
+    anomaly_score DOUBLE PRECISION,
+    predicted_demand INTEGER,
+    optimization_suggestions TEXT[],
+    calculated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_product_analytics_product
+    ON public.product_analytics (tenant_id, product_id, period_start DESC);
@@ -0,0 +1,70 @@
+-- Quality inspections
+-- Every purchase order receipt books its units into a batch (the batch number
+-- given on the receipt line, or the order number). Products flagged
+-- inspection_required get an incoming-goods inspection for each such batch;
+-- products with a certification expiry get a re-inspection ahead of it.
+-- Both rules are evaluated by a daily job and schedule idempotently: an
+-- automatically scheduled inspection is unique per rule and reference.
+-- Recording a result sets the batch's quality_status; a failed or recalled
+-- batch holds its units back from sale until it is dispositioned.
+
+ALTER TABLE public.product_attributes
+    ADD COLUMN IF NOT EXISTS inspection_required BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE TABLE IF NOT EXISTS public.product_batches (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    tenant_id UUID NOT NULL,
+    product_id UUID NOT NULL,
+    location_id UUID NOT NULL,
+    batch_number VARCHAR(100) NOT NULL,
+    quantity INTEGER NOT NULL CHECK (quantity >= 0),
+    quality_status VARCHAR(20) NOT NULL DEFAULT 'pending'
+        CHECK (quality_status IN ('pending', 'passed', 'failed', 'quarantined', 'recalled')),
+    purchase_order_id UUID,
+    disposition VARCHAR(30)
+        CHECK (disposition IN ('release', 'rework', 'scrap', 'return_to_supplier')),
+    dispositioned_at TIMESTAMPTZ,
+    dispositioned_by UUID,
+    received_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (tenant_id, product_id, location_id, batch_number)
+);
+
+-- Failed or recalled batches not yet dispositioned, subtracted from sellable availability
+CREATE INDEX IF NOT EXISTS idx_product_batches_blocked
+    ON public.product_batches (product_id, location_id)
+    WHERE quality_status IN ('failed', 'recalled') AND disposition IS NULL;
+
+CREATE TABLE IF NOT EXISTS public.quality_inspections (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    tenant_id UUID NOT NULL,
+    product_id UUID NOT NULL,
+    batch_id UUID REFERENCES public.product_batches(id) ON DELETE SET NULL,
+    inspection_type VARCHAR(50) NOT NULL,
+    scheduled_date TIMESTAMPTZ NOT NULL,
+    inspector_id UUID,
+    status VARCHAR(20) NOT NULL DEFAULT 'scheduled'
+        CHECK (status IN ('scheduled', 'passed', 'failed')),
+    priority INTEGER NOT NULL DEFAULT 3 CHECK (priority BETWEEN 1 AND 5),
+    -- Findings and notes of the recorded result
+    result JSONB,
+    -- Rule that scheduled the inspection, NULL when scheduled by hand
+    rule VARCHAR(50),
+    -- What the rule scheduled it for: the batch, or the certification expiry
+    reference TEXT,
+    completed_at TIMESTAMPTZ,
+    completed_by UUID,
+    overdue_notified_at TIMESTAMPTZ,
+    created_by UUID NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK ((status = 'scheduled') = (completed_at IS NULL))
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_quality_inspections_rule
+    ON public.quality_inspections (tenant_id, product_id, rule, reference)
+    WHERE rule IS NOT NULL;
+
+CREATE INDEX IF NOT EXISTS idx_quality_inspections_calendar
+    ON public.quality_inspections (tenant_id, scheduled_date)
+    WHERE status = 'scheduled';
@@ -0,0 +1,70 @@
+-- Sales territories
+-- A territory's rules (JSONB with the lists countries, regions,
+-- postal_prefixes, industries and sizes) select the customers its sales
+-- representative and account manager are responsible for; where territories
+-- overlap, the highest priority wins.
+-- customers keep the effective assignee in sales_representative_id and
+-- account_manager_id. Next to it they store what the territories derived,
+-- the territory it came from, and the explicit per-customer override, which
+-- wins. Assignments made before territories existed were made by hand, so
+-- they become overrides.
+-- customer_ownership_changes records every change of an effective assignee.
+-- It is served through the change log as entity type 'customer_ownership',
+-- which CRM syncs poll.
+
+CREATE TABLE IF NOT EXISTS public.sales_territories (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    tenant_id UUID NOT NULL,
+    name VARCHAR(100) NOT NULL,
+    description TEXT,
+    priority INTEGER NOT NULL DEFAULT 0,
+    rules JSONB NOT NULL DEFAULT '{}',
+    sales_representative_id UUID,
+    account_manager_id UUID,
+    is_active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_by UUID,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (tenant_id, name)
+);
+
+ALTER TABLE public.customers
+    ADD COLUMN IF NOT EXISTS territory_id UUID REFERENCES public.sales_territories(id) ON DELETE SET NULL,
+    ADD COLUMN IF NOT EXISTS derived_sales_representative_id UUID,
+    ADD COLUMN IF NOT EXISTS derived_account_manager_id UUID,
+    ADD COLUMN IF NOT EXISTS sales_representative_override_id UUID,
+    ADD COLUMN IF NOT EXISTS account_manager_override_id UUID;
+
+-- The assignee columns are read through to_jsonb so older customer tables
+-- without them still migrate
+UPDATE public.customers c
+SET sales_representative_override_id = (to_jsonb(c) ->> 'sales_representative_id')::uuid,
+    account_manager_override_id = (to_jsonb(c) ->> 'account_manager_id')::uuid
+WHERE c.sales_representative_override_id IS NULL
+  AND c.account_manager_override_id IS NULL
+  AND (to_jsonb(c) ->> 'sales_representative_id' IS NOT NULL
+    OR to_jsonb(c) ->> 'account_manager_id' IS NOT NULL);
+
+CREATE INDEX IF NOT EXISTS idx_customers_territory
+    ON public.customers(tenant_id, territory_id) WHERE territory_id IS NOT NULL;
+
+CREATE TABLE IF NOT EXISTS public.customer_ownership_changes (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    tenant_id UUID NOT NULL,
+    customer_id UUID NOT NULL REFERENCES public.customers(id) ON DELETE CASCADE,
+    role VARCHAR(30) NOT NULL CHECK (role IN ('sales_representative', 'account_manager')),
+    previous_assignee_id UUID,
+    new_assignee_id UUID,
+    source VARCHAR(20) NOT NULL CHECK (source IN ('override', 'territory', 'unassigned')),
+    territory_id UUID,
+    changed_by UUID,
+    changed_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_customer_ownership_changes_customer
+    ON public.customer_ownership_changes(tenant_id, customer_id, changed_at);
+
+DROP TRIGGER IF EXISTS trg_customer_ownership_changes_change_log ON public.customer_ownership_changes;
+CREATE TRIGGER trg_customer_ownership_changes_change_log
+    AFTER INSERT ON public.customer_ownership_changes
+    FOR EACH ROW EXECUTE FUNCTION public.record_entity_change('customer_ownership');
@@ -0,0 +1,38 @@
+-- Unit cost capture on inbound movements
+-- Inbound movements that come without a unit cost get one from the tenant's
+-- chain of cost sources (purchase order line, supplier catalog, product cost
+-- price, last receipt cost). cost_source records where a movement's cost came
+-- from; cost_needs_review flags costs only the weakest source of the chain
+-- supplied. Tenants with strict_costing reject inbound movements whose cost
+-- can't be resolved.
+-- A cost is restated by reversing the movement at its old cost
+-- (reversal_of) and booking it again at the new one (restatement_of), so
+-- reports on the cost layers see the change when it was made.
+
+ALTER TABLE inventory_transactions
+    ADD COLUMN IF NOT EXISTS cost_source VARCHAR(30)
+        CHECK (cost_source IN ('provided', 'purchase_order_line', 'supplier_catalog', 'product_cost_price', 'last_receipt', 'restated')),
+    ADD COLUMN IF NOT EXISTS cost_needs_review BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN IF NOT EXISTS reversal_of UUID REFERENCES inventory_transactions(id),
+    ADD COLUMN IF NOT EXISTS restatement_of UUID REFERENCES inventory_transactions(id);
+
+-- Costs recorded so far were sent with the movement
+UPDATE inventory_transactions
+SET cost_source = 'provided'
+WHERE cost_source IS NULL AND unit_cost IS NOT NULL AND quantity_change > 0;
+
+-- A movement is reversed at most once
+CREATE UNIQUE INDEX IF NOT EXISTS idx_inventory_transactions_reversal_of
+    ON inventory_transactions(reversal_of) WHERE reversal_of IS NOT NULL;
+
+CREATE INDEX IF NOT EXISTS idx_inventory_transactions_cost_source_date
+    ON inventory_transactions(cost_source, transaction_date) WHERE quantity_change > 0;
+
+CREATE TABLE IF NOT EXISTS public.inventory_costing_settings (
+    tenant_id UUID PRIMARY KEY,
+    cost_source_chain TEXT[] NOT NULL
+        DEFAULT ARRAY['purchase_order_line', 'supplier_catalog', 'product_cost_price', 'last_receipt'],
+    strict_costing BOOLEAN NOT NULL DEFAULT FALSE,
+    updated_by UUID,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,145 @@
+-- Tenant webhooks across event streams
+-- A subscription picks the event sources it wants (customer, inventory,
+-- auth) and optionally single event types as "source.type". The dispatcher
+-- reads every source after its mark in webhook_source_marks and queues one
+-- delivery per matching subscription. Deliveries are numbered per
+-- (subscription, source) and sent in that order; a failing endpoint holds up
+-- only that source of that subscription. (subscription_id, source, source_id)
+-- is unique so replaying a source after a crash queues nothing twice.
+-- inventory_events is the inventory event stream: stock reaching zero or
+-- falling to the reorder point, written by a trigger on location_items, and
+-- stock transfers completed, written by a trigger on stock_transfers.
+
+CREATE TABLE IF NOT EXISTS public.inventory_events (
+    id BIGSERIAL PRIMARY KEY,
+    tenant_id UUID NOT NULL,
+    event_type VARCHAR(40) NOT NULL CHECK (event_type IN ('stockout', 'below_reorder', 'transfer_completed')),
+    product_id UUID NOT NULL,
+    location_id UUID,
+    data JSONB NOT NULL DEFAULT '{}',
+    occurred_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_inventory_events_order ON public.inventory_events(occurred_at, id);
+
+-- Only crossings are events: a location that stays out of stock emits nothing more
+CREATE OR REPLACE FUNCTION public.emit_stock_level_events() RETURNS TRIGGER AS $$
+DECLARE
+    v_tenant_id UUID;
+    v_sku TEXT;
+BEGIN
+    IF NEW.quantity_available = OLD.quantity_available THEN
+        RETURN NEW;
+    END IF;
+
+    SELECT tenant_id, sku INTO v_tenant_id, v_sku FROM products WHERE id = NEW.product_id;
+    IF v_tenant_id IS NULL THEN
+        RETURN NEW;
+    END IF;
+
+    IF NEW.quantity_available <= 0 AND OLD.quantity_available > 0 THEN
+        INSERT INTO public.inventory_events (tenant_id, event_type, product_id, location_id, data)
+        VALUES (v_tenant_id, 'stockout', NEW.product_id, NEW.location_id,
+                jsonb_build_object('sku', v_sku, 'quantity_available', NEW.quantity_available,
+                                   'previous_quantity', OLD.quantity_available));
+    ELSIF NEW.reorder_point > 0 AND NEW.quantity_available <= NEW.reorder_point
+          AND OLD.quantity_available > NEW.reorder_point THEN
+        INSERT INTO public.inventory_events (tenant_id, event_type, product_id, location_id, data)
+        VALUES (v_tenant_id, 'below_reorder', NEW.product_id, NEW.location_id,
+                jsonb_build_object('sku', v_sku, 'quantity_available', NEW.quantity_available,
+                                   'reorder_point', NEW.reorder_point));
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS trg_location_items_stock_events ON location_items;
+CREATE TRIGGER trg_location_items_stock_events
+    AFTER UPDATE OF quantity_available ON location_items
+    FOR EACH ROW EXECUTE FUNCTION public.emit_stock_level_events();
+
+CREATE OR REPLACE FUNCTION public.emit_transfer_completed_event() RETURNS TRIGGER AS $$
+DECLARE
+    v_tenant_id UUID;
+    v_sku TEXT;
+BEGIN
+    IF NEW.status::TEXT <> 'completed' OR (TG_OP = 'UPDATE' AND OLD.status::TEXT = 'completed') THEN
+        RETURN NEW;
+    END IF;
+
+    SELECT tenant_id, sku INTO v_tenant_id, v_sku FROM products WHERE id = NEW.product_id;
+    IF v_tenant_id IS NULL THEN
+        RETURN NEW;
+    END IF;
+
+    INSERT INTO public.inventory_events (tenant_id, event_type, product_id, location_id, data)
+    VALUES (v_tenant_id, 'transfer_completed', NEW.product_id, NEW.to_location_id,
+            jsonb_build_object('transfer_id', NEW.id, 'sku', v_sku, 'from_location_id', NEW.from_location_id,
+                               'to_location_id', NEW.to_location_id, 'quantity', NEW.quantity,
+                               'quantity_received', NEW.quantity_received));
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+-- stock_transfers is created by the inventory service where it is deployed
+DO $$
+BEGIN
+    IF to_regclass('public.stock_transfers') IS NOT NULL THEN
+        DROP TRIGGER IF EXISTS trg_stock_transfers_completed_event ON stock_transfers;
+        CREATE TRIGGER trg_stock_transfers_completed_event
+            AFTER INSERT OR UPDATE OF status ON stock_transfers
+            FOR EACH ROW EXECUTE FUNCTION public.emit_transfer_completed_event();
+    END IF;
+END;
+$$;
+
+CREATE TABLE IF NOT EXISTS public.webhook_subscriptions (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    tenant_id UUID NOT NULL,
+    name VARCHAR(200) NOT NULL,
+    url TEXT NOT NULL,
+    secret VARCHAR(128) NOT NULL,
+    sources TEXT[] NOT NULL,
+    -- "source.type"; empty for every type of the chosen sources
+    event_types TEXT[] NOT NULL DEFAULT '{}',
+    active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_by UUID,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CHECK (cardinality(sources) > 0 AND sources <@ ARRAY['customer', 'inventory', 'auth'])
+);
+
+CREATE INDEX IF NOT EXISTS idx_webhook_subscriptions_tenant ON public.webhook_subscriptions(tenant_id) WHERE active;
+
+CREATE TABLE IF NOT EXISTS public.webhook_source_marks (
+    source VARCHAR(20) PRIMARY KEY,
+    position TIMESTAMPTZ NOT NULL,
+    source_id VARCHAR(255) NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS public.webhook_deliveries (
+    id BIGSERIAL PRIMARY KEY,
+    subscription_id UUID NOT NULL REFERENCES public.webhook_subscriptions(id) ON DELETE CASCADE,
+    tenant_id UUID NOT NULL,
+    source VARCHAR(20) NOT NULL CHECK (source IN ('customer', 'inventory', 'auth')),
+    event_type VARCHAR(100) NOT NULL,
+    source_id VARCHAR(255) NOT NULL,
+    sequence BIGINT NOT NULL,
+    occurred_at TIMESTAMPTZ NOT NULL,
+    data JSONB NOT NULL,
+    status VARCHAR(20) NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'delivered', 'failed')),
+    attempts INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_error TEXT,
+    response_status INTEGER,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    delivered_at TIMESTAMPTZ,
+    UNIQUE (subscription_id, source, sequence),
+    UNIQUE (subscription_id, source, source_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_webhook_deliveries_pending
+    ON public.webhook_deliveries(subscription_id, source, sequence) WHERE status = 'pending';
+CREATE INDEX IF NOT EXISTS idx_webhook_deliveries_tenant
+    ON public.webhook_deliveries(tenant_id, created_at DESC, id DESC);
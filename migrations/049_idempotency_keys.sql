@@ -0,0 +1,25 @@
+-- Idempotency keys of create requests
+-- A create request sent with an `Idempotency-Key` header claims the key for
+-- its tenant and endpoint (scope) while it runs. Once it answered 201, its
+-- response is kept here until expires_at and replayed to requests reusing the
+-- key; a claim whose request never finished is taken over after locked_until.
+
+CREATE TABLE IF NOT EXISTS public.idempotency_keys (
+    tenant_id UUID NOT NULL,
+    scope VARCHAR(50) NOT NULL,
+    idempotency_key VARCHAR(255) NOT NULL,
+    request_hash VARCHAR(64) NOT NULL,
+    state VARCHAR(20) NOT NULL DEFAULT 'pending',
+    response_status INTEGER,
+    content_type TEXT,
+    location TEXT,
+    entity_id UUID,
+    response_body BYTEA,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    locked_until TIMESTAMPTZ NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (tenant_id, scope, idempotency_key),
+    CHECK (state IN ('pending', 'completed'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_idempotency_keys_expires_at ON public.idempotency_keys (expires_at);
@@ -0,0 +1,54 @@
+-- Purchase order receipts
+-- Purchase order lines get a receiving status: open, partially_received,
+-- received, closed_short (with the reason the remaining units are no longer
+-- expected) or cancelled. Every receipt records one event per line it books
+-- units on or closes short, with who received it and when; together they
+-- make up the order's receipt history.
+
+DO $$
+BEGIN
+    IF to_regclass('purchase_order_lines') IS NOT NULL THEN
+        ALTER TABLE purchase_order_lines
+            ADD COLUMN IF NOT EXISTS status VARCHAR(20) NOT NULL DEFAULT 'open',
+            ADD COLUMN IF NOT EXISTS closed_short_reason TEXT;
+
+        UPDATE purchase_order_lines
+        SET status = CASE
+            WHEN quantity_received >= quantity_ordered THEN 'received'
+            WHEN quantity_received > 0 THEN 'partially_received'
+            ELSE 'open'
+        END
+        WHERE status = 'open';
+
+        IF NOT EXISTS (SELECT 1 FROM pg_constraint WHERE conname = 'purchase_order_lines_status_check') THEN
+            ALTER TABLE purchase_order_lines ADD CONSTRAINT purchase_order_lines_status_check
+                CHECK (status IN ('open', 'partially_received', 'received', 'closed_short', 'cancelled'));
+        END IF;
+    END IF;
+
+    IF EXISTS (SELECT 1 FROM pg_type WHERE typname = 'order_status') THEN
+        ALTER TYPE order_status ADD VALUE IF NOT EXISTS 'closed_short' AFTER 'received';
+    END IF;
+END $$;
+
+CREATE TABLE IF NOT EXISTS public.purchase_order_receipt_events (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    tenant_id UUID NOT NULL,
+    receipt_id UUID NOT NULL,
+    purchase_order_id UUID NOT NULL,
+    line_id UUID NOT NULL,
+    product_id UUID NOT NULL,
+    event_type VARCHAR(20) NOT NULL CHECK (event_type IN ('received', 'closed_short')),
+    quantity INTEGER NOT NULL CHECK (quantity >= 0),
+    bin_code VARCHAR(50),
+    batch_number VARCHAR(100),
+    serial_numbers TEXT[],
+    movement_id UUID,
+    reason TEXT,
+    line_status VARCHAR(20) NOT NULL,
+    received_by UUID NOT NULL,
+    received_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_purchase_order_receipt_events_order
+    ON public.purchase_order_receipt_events (tenant_id, purchase_order_id, received_at);
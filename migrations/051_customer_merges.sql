@@ -0,0 +1,25 @@
+-- Customer merges
+-- Merging a duplicate customer into another one moves its orders, contacts
+-- and tax jurisdictions to the target and soft deletes it. customer_merges
+-- is the audit trail of merges: which customer went into which, the strategy
+-- that picked the target's field values, the fields taken from the source
+-- and how many records moved. It is written in the merge's transaction, so
+-- a row means the merge is complete; one row per source makes running a
+-- merge again safe.
+
+CREATE TABLE IF NOT EXISTS public.customer_merges (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    tenant_id UUID NOT NULL,
+    source_customer_id UUID NOT NULL REFERENCES public.customers(id),
+    target_customer_id UUID NOT NULL REFERENCES public.customers(id),
+    strategy JSONB NOT NULL,
+    fields_from_source JSONB NOT NULL DEFAULT '[]',
+    reassigned JSONB NOT NULL DEFAULT '{}',
+    merged_by UUID NOT NULL,
+    merged_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (tenant_id, source_customer_id),
+    CHECK (source_customer_id <> target_customer_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_customer_merges_target
+    ON public.customer_merges(tenant_id, target_customer_id);
@@ -0,0 +1,7 @@
+-- Sliding sessions per tenant
+-- With sliding sessions, an authenticated request made when the access token
+-- has little of its lifetime left gets a fresh token in the
+-- X-Refreshed-Access-Token response header. Off unless the tenant turns it on.
+
+ALTER TABLE public.tenant_session_limits
+    ADD COLUMN IF NOT EXISTS sliding_sessions BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,10 @@
+-- Keyset pagination of customer searches
+-- Cursor pages are read with WHERE (sort_column, id) > (key, id) ORDER BY
+-- sort_column, id; these indexes serve the default sort by legal name and
+-- the sort by creation date without a sort step.
+
+CREATE INDEX IF NOT EXISTS idx_customers_keyset_legal_name
+    ON public.customers(tenant_id, legal_name, id);
+
+CREATE INDEX IF NOT EXISTS idx_customers_keyset_created_at
+    ON public.customers(tenant_id, created_at, id);
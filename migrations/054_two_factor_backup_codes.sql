@@ -0,0 +1,17 @@
+-- Two-factor backup codes
+-- One-time codes issued when a user enables 2FA, for signing in without the
+-- authenticator app. Only Argon2 hashes are stored; used_at is set when a
+-- code signs the user in, and a used code never matches again. Enabling 2FA
+-- again replaces the user's codes, disabling it removes them.
+
+CREATE TABLE IF NOT EXISTS public.two_factor_backup_codes (
+    id BIGSERIAL PRIMARY KEY,
+    tenant_id UUID NOT NULL,
+    user_id UUID NOT NULL,
+    code_hash TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    used_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_two_factor_backup_codes_unused
+    ON public.two_factor_backup_codes(tenant_id, user_id) WHERE used_at IS NULL;
@@ -0,0 +1,35 @@
+-- Change log sequence numbers in commit order
+-- change_seq used to be drawn when a row was inserted, but transactions commit
+-- out of order: a client whose cursor had passed a number still held by an
+-- open transaction never received that change. Rows are now written without a
+-- number, together with the id of the writing transaction (txid). The sync
+-- feed numbers them once that transaction and every older one have ended, that
+-- is when txid is below the xmin of its snapshot, one caller at a time. A
+-- number handed out later is then always higher than every number a client
+-- could already have seen.
+
+ALTER TABLE public.change_log ADD COLUMN IF NOT EXISTS log_id BIGSERIAL;
+ALTER TABLE public.change_log ADD COLUMN IF NOT EXISTS txid xid8 NOT NULL DEFAULT pg_current_xact_id();
+
+DO $$
+BEGIN
+    IF EXISTS (
+        SELECT 1 FROM pg_constraint
+        WHERE conname = 'change_log_pkey' AND conrelid = 'public.change_log'::regclass
+          AND pg_get_constraintdef(oid) = 'PRIMARY KEY (change_seq)'
+    ) THEN
+        ALTER TABLE public.change_log DROP CONSTRAINT change_log_pkey;
+        ALTER TABLE public.change_log ADD CONSTRAINT change_log_pkey PRIMARY KEY (log_id);
+    END IF;
+END;
+$$;
+
+-- Numbered by the sync feed, see crates/api/src/sync.rs
+ALTER TABLE public.change_log ALTER COLUMN change_seq DROP NOT NULL;
+ALTER TABLE public.change_log ALTER COLUMN change_seq DROP DEFAULT;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_change_log_change_seq
+    ON public.change_log(change_seq);
+
+CREATE INDEX IF NOT EXISTS idx_change_log_unnumbered
+    ON public.change_log(txid, log_id) WHERE change_seq IS NULL;
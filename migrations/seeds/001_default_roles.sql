@@ -4,11 +4,11 @@
 -- Create default roles for the tenant
 INSERT INTO roles (id, name, description, permissions, is_system, is_active, created_at, updated_at) VALUES
     (gen_random_uuid(), 'admin', 'System Administrator',
-     '["users:read", "users:write", "users:delete", "products:read", "products:write", "products:delete", "inventory:read", "inventory:write", "customers:read", "customers:write", "suppliers:read", "suppliers:write", "reports:read", "settings:write"]',
+     '["users:read", "users:write", "users:delete", "products:read", "products:write", "products:delete", "products:price_guard_override", "inventory:read", "inventory:write", "inventory:approve_transfers", "customers:read", "customers:write", "communications:manage", "suppliers:read", "suppliers:write", "reports:read", "settings:write"]',
      true, true, NOW(), NOW()),
 
     (gen_random_uuid(), 'manager', 'Manager',
-     '["products:read", "products:write", "inventory:read", "inventory:write", "customers:read", "customers:write", "suppliers:read", "suppliers:write", "reports:read"]',
+     '["products:read", "products:write", "inventory:read", "inventory:write", "inventory:approve_transfers", "customers:read", "customers:write", "suppliers:read", "suppliers:write", "reports:read"]',
      true, true, NOW(), NOW()),
 
     (gen_random_uuid(), 'employee', 'Employee',
@@ -22,15 +22,15 @@ INSERT INTO roles (id, name, description, permissions, is_system, is_active, cre
 -- Create default permission groups
 INSERT INTO permission_groups (id, name, description, permissions, is_active, created_at, updated_at) VALUES
     (gen_random_uuid(), 'product_management', 'Product Management Permissions',
-     '["products:read", "products:write", "products:delete"]',
+     '["products:read", "products:write", "products:delete", "products:price_guard_override"]',
      true, NOW(), NOW()),
 
     (gen_random_uuid(), 'inventory_management', 'Inventory Management Permissions',
-     '["inventory:read", "inventory:write", "inventory:adjust", "inventory:transfer"]',
+     '["inventory:read", "inventory:write", "inventory:adjust", "inventory:transfer", "inventory:approve_transfers"]',
      true, NOW(), NOW()),
 
     (gen_random_uuid(), 'customer_management', 'Customer Management Permissions',
-     '["customers:read", "customers:write", "customers:delete"]',
+     '["customers:read", "customers:write", "customers:delete", "communications:manage"]',
      true, NOW(), NOW()),
 
     (gen_random_uuid(), 'supplier_management', 'Supplier Management Permissions',
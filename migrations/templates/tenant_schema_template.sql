@@ -0,0 +1,54 @@
+-- Tenant schema template for multi-tenant setup
+-- This file is referenced by the Rust deploy module
+-- Schema version: 55
+-- The shared migration the tables below match. Raise it with a migration that
+-- changes a table copied here; tenants recorded below it are reported behind.
+
+-- Create schema-specific tables for tenant isolation
+CREATE SCHEMA IF NOT EXISTS {TENANT_SCHEMA};
+
+-- Set search path for this schema
+SET search_path TO {TENANT_SCHEMA}, public;
+
+-- Copy all tables from public schema to tenant schema
+CREATE TABLE {TENANT_SCHEMA}.users (LIKE public.users INCLUDING ALL);
+CREATE TABLE {TENANT_SCHEMA}.roles (LIKE public.roles INCLUDING ALL);
+CREATE TABLE {TENANT_SCHEMA}.user_permissions (LIKE public.user_permissions INCLUDING ALL);
+CREATE TABLE {TENANT_SCHEMA}.products (LIKE public.products INCLUDING ALL);
+CREATE TABLE {TENANT_SCHEMA}.customers (LIKE public.customers INCLUDING ALL);
+CREATE TABLE {TENANT_SCHEMA}.suppliers (LIKE public.suppliers INCLUDING ALL);
+CREATE TABLE {TENANT_SCHEMA}.locations (LIKE public.locations INCLUDING ALL);
+CREATE TABLE {TENANT_SCHEMA}.location_items (LIKE public.location_items INCLUDING ALL);
+CREATE TABLE {TENANT_SCHEMA}.inventory_transactions (LIKE public.inventory_transactions INCLUDING ALL);
+CREATE TABLE {TENANT_SCHEMA}.currencies (LIKE public.currencies INCLUDING ALL);
+CREATE TABLE {TENANT_SCHEMA}.countries (LIKE public.countries INCLUDING ALL);
+CREATE TABLE {TENANT_SCHEMA}.units_of_measure (LIKE public.units_of_measure INCLUDING ALL);
+
+-- LIKE does not copy triggers; the tenant's tables need the shared ones too.
+-- Dropped first so `tenant migrate-all` can recreate them on existing schemas.
+
+-- Differential sync change log (005_sync_change_log.sql)
+DROP TRIGGER IF EXISTS trg_customers_change_log ON {TENANT_SCHEMA}.customers;
+CREATE TRIGGER trg_customers_change_log
+    AFTER INSERT OR UPDATE OR DELETE ON {TENANT_SCHEMA}.customers
+    FOR EACH ROW EXECUTE FUNCTION public.record_entity_change('customer');
+DROP TRIGGER IF EXISTS trg_products_change_log ON {TENANT_SCHEMA}.products;
+CREATE TRIGGER trg_products_change_log
+    AFTER INSERT OR UPDATE OR DELETE ON {TENANT_SCHEMA}.products
+    FOR EACH ROW EXECUTE FUNCTION public.record_entity_change('product');
+
+-- Barcode uniqueness (015_product_barcodes.sql); existing barcodes are mirrored
+-- first, duplicates are left to the barcode report as in public
+INSERT INTO public.product_barcodes (tenant_id, barcode_key, barcode, product_id)
+SELECT tenant_id, public.barcode_uniqueness_key(barcode), barcode, id
+FROM {TENANT_SCHEMA}.products
+WHERE NULLIF(barcode, '') IS NOT NULL
+ORDER BY created_at
+ON CONFLICT DO NOTHING;
+DROP TRIGGER IF EXISTS trg_products_barcode ON {TENANT_SCHEMA}.products;
+CREATE TRIGGER trg_products_barcode
+    AFTER INSERT OR UPDATE OF barcode OR DELETE ON {TENANT_SCHEMA}.products
+    FOR EACH ROW EXECUTE FUNCTION public.sync_product_barcode('product');
+
+-- Reset search path
+SET search_path TO public;
\ No newline at end of file
@@ -148,6 +148,55 @@ create_tenant_record() {
     "
 }
 
+# Function to record a provisioning step; the tenant health check reports
+# steps that never reached 'complete'
+record_step() {
+    local tenant_id="$1"
+    local step="$2"
+    local status="$3"
+
+    psql -v ON_ERROR_STOP=1 -q "$DATABASE_URL" -c "
+        INSERT INTO public.tenant_provisioning_steps (tenant_id, step, status)
+        VALUES ('$tenant_id'::uuid, '$step', '$status')
+        ON CONFLICT (tenant_id, step) DO UPDATE SET status = EXCLUDED.status, updated_at = NOW();
+    "
+}
+
+# Function to run a provisioning step, recording it as running, then complete or failed
+run_step() {
+    local tenant_id="$1"
+    local step="$2"
+    shift 2
+
+    record_step "$tenant_id" "$step" "running"
+    if "$@"; then
+        record_step "$tenant_id" "$step" "complete"
+    else
+        record_step "$tenant_id" "$step" "failed"
+        print_error "Provisioning step failed: $step"
+        exit 1
+    fi
+}
+
+# Function to record the tenant template's schema version for the new schema
+record_schema_version() {
+    local tenant_id="$1"
+    local template_file="migrations/templates/tenant_schema_template.sql"
+    local version
+
+    version=$(sed -n 's/^-- Schema version: *\([0-9][0-9]*\).*/\1/p' "$template_file" | head -n 1)
+    if [[ -z "$version" ]]; then
+        print_error "Schema template declares no version: $template_file"
+        return 1
+    fi
+
+    psql -v ON_ERROR_STOP=1 -q "$DATABASE_URL" -c "
+        INSERT INTO public.tenant_schema_migrations (tenant_id, version, description, success)
+        VALUES ('$tenant_id'::uuid, $version, 'tenant schema template', TRUE)
+        ON CONFLICT (tenant_id, version) DO UPDATE SET success = TRUE, applied_at = NOW();
+    "
+}
+
 # Function to create tenant schema
 create_tenant_schema() {
     local schema_name="$1"
@@ -161,7 +210,7 @@ create_tenant_schema() {
     print_status "Creating tenant schema: $schema_name"
 
     # Read and process schema template
-    local template_file="migrations/002_tenant_schema_template.sql"
+    local template_file="migrations/templates/tenant_schema_template.sql"
     if [[ ! -f "$template_file" ]]; then
         print_error "Schema template not found: $template_file"
         exit 1
@@ -172,7 +221,7 @@ create_tenant_schema() {
     processed_sql=$(sed "s/{TENANT_SCHEMA}/$schema_name/g" "$template_file")
 
     # Execute schema creation
-    echo "$processed_sql" | psql "$DATABASE_URL"
+    echo "$processed_sql" | psql -v ON_ERROR_STOP=1 "$DATABASE_URL" || return 1
 
     print_status "Tenant schema created successfully"
 }
@@ -194,7 +243,7 @@ seed_tenant_data() {
     if [[ -f "$roles_file" ]]; then
         local processed_sql
         processed_sql=$(sed "s/{TENANT_SCHEMA}/$schema_name/g" "$roles_file")
-        echo "$processed_sql" | psql "$DATABASE_URL"
+        echo "$processed_sql" | psql -v ON_ERROR_STOP=1 "$DATABASE_URL" || return 1
         print_status "Default roles created"
     fi
 
@@ -203,7 +252,7 @@ seed_tenant_data() {
     if [[ -f "$ref_data_file" ]]; then
         local processed_sql
         processed_sql=$(sed "s/{TENANT_SCHEMA}/$schema_name/g; s/{TENANT_NAME}/$tenant_name/g; s/{TENANT_DOMAIN}/$tenant_domain/g" "$ref_data_file")
-        echo "$processed_sql" | psql "$DATABASE_URL"
+        echo "$processed_sql" | psql -v ON_ERROR_STOP=1 "$DATABASE_URL" || return 1
         print_status "Reference data created"
     fi
 
@@ -212,7 +261,7 @@ seed_tenant_data() {
     if [[ -f "$admin_file" ]]; then
         local processed_sql
         processed_sql=$(sed "s/{TENANT_SCHEMA}/$schema_name/g; s/{TENANT_ID}/$tenant_id/g; s/{ADMIN_EMAIL}/$admin_email/g; s/{ADMIN_PASSWORD_HASH}/$admin_password_hash/g; s/{ADMIN_USER_ID}/$admin_user_id/g" "$admin_file")
-        echo "$processed_sql" | psql "$DATABASE_URL"
+        echo "$processed_sql" | psql -v ON_ERROR_STOP=1 "$DATABASE_URL" || return 1
         print_status "Admin user created"
     fi
 
@@ -432,10 +481,13 @@ main() {
     create_tenant_record "$TENANT_ID" "$TENANT_NAME" "$SCHEMA_NAME"
 
     # Step 2: Create tenant schema
-    create_tenant_schema "$SCHEMA_NAME" "$TENANT_ID" "$TENANT_NAME" "$TENANT_DOMAIN" "$ADMIN_EMAIL" "$ADMIN_PASSWORD_HASH" "$ADMIN_USER_ID"
+    run_step "$TENANT_ID" "create tenant schema" \
+        create_tenant_schema "$SCHEMA_NAME" "$TENANT_ID" "$TENANT_NAME" "$TENANT_DOMAIN" "$ADMIN_EMAIL" "$ADMIN_PASSWORD_HASH" "$ADMIN_USER_ID"
+    record_schema_version "$TENANT_ID"
 
     # Step 3: Seed default data
-    seed_tenant_data "$SCHEMA_NAME" "$TENANT_ID" "$TENANT_NAME" "$TENANT_DOMAIN" "$ADMIN_EMAIL" "$ADMIN_PASSWORD_HASH" "$ADMIN_USER_ID"
+    run_step "$TENANT_ID" "seed default data" \
+        seed_tenant_data "$SCHEMA_NAME" "$TENANT_ID" "$TENANT_NAME" "$TENANT_DOMAIN" "$ADMIN_EMAIL" "$ADMIN_PASSWORD_HASH" "$ADMIN_USER_ID"
 
     # Step 4: Verify creation
     if verify_tenant "$SCHEMA_NAME" "$TENANT_ID"; then